@@ -7,12 +7,25 @@
 //! - FADT (FACP) with DSDT pointer and basic PM blocks
 //! - MADT (APIC) with LAPICs, IOAPIC, ISA overrides (timer + SCI)
 //! - HPET table
-//! - Minimal DSDT AML exposing PCI0 + HPET + CPU objects
+//! - Optional TPM2 table (TPM 2.0 CRB interface)
+//! - Optional SRAT + SLIT (NUMA processor/memory affinity and node distances)
+//! - Minimal DSDT AML exposing PCI0 + HPET + CPU objects, and `\_GPE` handlers for the GPEs
+//!   reserved for Aero event sources ([`AERO_GPE_PCI_HOTPLUG`] etc.)
+//!
+//! It can also decode the resources a published table set advertises (see
+//! [`read_acpi_resources`]), for cross-checking the tables against the platform.
 
+mod resources;
 mod tables;
 
+pub use resources::{
+    parse_dsdt_resources, parse_fadt_resources, parse_hpet_resources, parse_madt_resources,
+    parse_mcfg_resources, parse_resource_template, read_acpi_resources, AcpiClaim, AcpiParseError,
+    AcpiPrtEntry, AcpiResource, AcpiResourceSummary,
+};
+
 pub use tables::{
-    AcpiConfig, AcpiPlacement, AcpiTables, PhysicalMemory, DEFAULT_ACPI_ALIGNMENT,
-    DEFAULT_ACPI_NVS_SIZE, FADT_FLAG_FIX_RTC, FADT_FLAG_PWR_BUTTON, FADT_FLAG_RESET_REG_SUP,
-    FADT_FLAG_SLP_BUTTON,
+    AcpiConfig, AcpiNumaNode, AcpiPlacement, AcpiTables, PhysicalMemory, AERO_GPE_BATTERY,
+    AERO_GPE_LID, AERO_GPE_PCI_HOTPLUG, DEFAULT_ACPI_ALIGNMENT, DEFAULT_ACPI_NVS_SIZE,
+    FADT_FLAG_FIX_RTC, FADT_FLAG_PWR_BUTTON, FADT_FLAG_RESET_REG_SUP, FADT_FLAG_SLP_BUTTON,
 };
@@ -0,0 +1,900 @@
+//! Decoding of the platform resources an ACPI table set advertises.
+//!
+//! This is the read side of [`crate::AcpiTables`]: given a published table set it recovers which
+//! I/O ports, MMIO regions, IRQs and DMA channels the firmware tells the OS about. It understands
+//! the static AML subset the generator emits (scopes, devices, named objects holding integers,
+//! strings, buffers and packages, SystemIO operation regions) and skips method bodies; it is meant
+//! for platform diagnostics, not as a general AML interpreter.
+
+use core::fmt;
+
+/// One resource decoded from a `_CRS` template or a fixed ACPI table field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiResource {
+    /// I/O ports consumed by the device.
+    Io { base: u16, len: u16 },
+    /// MMIO consumed by the device.
+    Memory { base: u64, len: u64 },
+    /// An I/O window a bridge produces for its children (e.g. the PCI root bridge).
+    IoWindow { base: u64, len: u64 },
+    /// A memory window a bridge produces for its children.
+    MemoryWindow { base: u64, len: u64 },
+    /// A bus number range a bridge produces.
+    BusRange { start: u16, len: u16 },
+    /// A legacy (ISA) IRQ or GSI.
+    Irq(u32),
+    /// An ISA DMA channel.
+    Dma(u8),
+}
+
+/// A resource together with the ACPI object or table field that advertises it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcpiClaim {
+    /// Where the resource is advertised, e.g. `\_SB_.SYS0._CRS` or `FADT.PM1a_EVT_BLK`.
+    pub source: String,
+    pub resource: AcpiResource,
+}
+
+/// One static `_PRT` entry (with a zero `Source`, i.e. a direct GSI mapping).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcpiPrtEntry {
+    /// PCI device number the entry applies to (all functions).
+    pub device: u8,
+    /// Interrupt pin, 0 = INTA# .. 3 = INTD#.
+    pub pin: u8,
+    pub gsi: u32,
+}
+
+/// Resources advertised by a full ACPI table set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AcpiResourceSummary {
+    pub claims: Vec<AcpiClaim>,
+    /// `_PRT` of the PCI root bridge (bus 0).
+    pub prt: Vec<AcpiPrtEntry>,
+    /// FADT `SCI_INT`.
+    pub sci_irq: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcpiParseError {
+    /// A table or structure ended before a field it declares.
+    Truncated(&'static str),
+    /// A table header carried an unexpected signature.
+    BadSignature {
+        expected: &'static str,
+        found: [u8; 4],
+    },
+    /// The RSDP does not reference any system description table.
+    NoRootTable,
+}
+
+impl fmt::Display for AcpiParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcpiParseError::Truncated(what) => write!(f, "truncated {what}"),
+            AcpiParseError::BadSignature { expected, found } => write!(
+                f,
+                "expected {expected} signature, found {:?}",
+                String::from_utf8_lossy(found)
+            ),
+            AcpiParseError::NoRootTable => write!(f, "RSDP references neither an RSDT nor an XSDT"),
+        }
+    }
+}
+
+impl std::error::Error for AcpiParseError {}
+
+fn u16_at(bytes: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(off..off + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(off..off + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u64_at(bytes: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(off..off + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Decodes a resource template (the contents of a `_CRS` buffer) up to its end tag.
+///
+/// Descriptors that carry no address/IRQ/DMA information (vendor, start/end dependent functions,
+/// ...) are skipped. Bit masks in IRQ and DMA descriptors expand to one entry per set bit.
+pub fn parse_resource_template(bytes: &[u8]) -> Result<Vec<AcpiResource>, AcpiParseError> {
+    const TRUNCATED: AcpiParseError = AcpiParseError::Truncated("resource template");
+
+    let mut out = Vec::new();
+    let mut pos = 0usize;
+    while pos < bytes.len() {
+        let tag = bytes[pos];
+        if tag & 0x80 == 0 {
+            // Small resource: bits 6:3 name, bits 2:0 length.
+            let len = usize::from(tag & 0x07);
+            let body = bytes.get(pos + 1..pos + 1 + len).ok_or(TRUNCATED)?;
+            match (tag >> 3) & 0x0F {
+                // IRQ descriptor.
+                0x04 => {
+                    let mask = u16_at(body, 0).ok_or(TRUNCATED)?;
+                    out.extend(
+                        (0..16u32)
+                            .filter(|irq| mask & (1u16 << irq) != 0)
+                            .map(AcpiResource::Irq),
+                    );
+                }
+                // DMA descriptor.
+                0x05 => {
+                    let mask = *body.first().ok_or(TRUNCATED)?;
+                    out.extend(
+                        (0..8u8)
+                            .filter(|ch| mask & (1u8 << ch) != 0)
+                            .map(AcpiResource::Dma),
+                    );
+                }
+                // I/O port descriptor: base is the range minimum.
+                0x08 => {
+                    let base = u16_at(body, 1).ok_or(TRUNCATED)?;
+                    let len = *body.get(6).ok_or(TRUNCATED)?;
+                    out.push(AcpiResource::Io {
+                        base,
+                        len: u16::from(len),
+                    });
+                }
+                // Fixed location I/O port descriptor (10-bit decode).
+                0x09 => {
+                    let base = u16_at(body, 0).ok_or(TRUNCATED)? & 0x03FF;
+                    let len = *body.get(2).ok_or(TRUNCATED)?;
+                    out.push(AcpiResource::Io {
+                        base,
+                        len: u16::from(len),
+                    });
+                }
+                // End tag.
+                0x0F => return Ok(out),
+                _ => {}
+            }
+            pos += 1 + len;
+        } else {
+            let len = usize::from(u16_at(bytes, pos + 1).ok_or(TRUNCATED)?);
+            let body = bytes.get(pos + 3..pos + 3 + len).ok_or(TRUNCATED)?;
+            match tag & 0x7F {
+                // 32-bit memory range descriptor: base is the range minimum.
+                0x05 => out.push(AcpiResource::Memory {
+                    base: u64::from(u32_at(body, 1).ok_or(TRUNCATED)?),
+                    len: u64::from(u32_at(body, 13).ok_or(TRUNCATED)?),
+                }),
+                // 32-bit fixed memory range descriptor.
+                0x06 => out.push(AcpiResource::Memory {
+                    base: u64::from(u32_at(body, 1).ok_or(TRUNCATED)?),
+                    len: u64::from(u32_at(body, 5).ok_or(TRUNCATED)?),
+                }),
+                // DWord / Word / QWord address space descriptors.
+                0x07 => out.extend(address_space(body, 4)?),
+                0x08 => out.extend(address_space(body, 2)?),
+                0x0A => out.extend(address_space(body, 8)?),
+                // Extended interrupt descriptor.
+                0x09 => {
+                    let count = usize::from(*body.get(1).ok_or(TRUNCATED)?);
+                    for i in 0..count {
+                        out.push(AcpiResource::Irq(u32_at(body, 2 + i * 4).ok_or(TRUNCATED)?));
+                    }
+                }
+                _ => {}
+            }
+            pos += 3 + len;
+        }
+    }
+    Err(TRUNCATED)
+}
+
+/// Decodes an address space descriptor body whose range fields are `width` bytes wide.
+fn address_space(body: &[u8], width: usize) -> Result<Option<AcpiResource>, AcpiParseError> {
+    const TRUNCATED: AcpiParseError = AcpiParseError::Truncated("address space descriptor");
+
+    let field = |index: usize| -> Result<u64, AcpiParseError> {
+        let off = 3 + index * width;
+        match width {
+            2 => u16_at(body, off).map(u64::from),
+            4 => u32_at(body, off).map(u64::from),
+            _ => u64_at(body, off),
+        }
+        .ok_or(TRUNCATED)
+    };
+
+    let resource_type = *body.first().ok_or(TRUNCATED)?;
+    let general_flags = *body.get(1).ok_or(TRUNCATED)?;
+    // Fields: granularity, min, max, translation, length.
+    let base = field(1)?;
+    let len = field(4)?;
+    // GeneralFlags bit 0: 0 = ResourceProducer, 1 = ResourceConsumer.
+    let producer = general_flags & 0x01 == 0;
+
+    Ok(match (resource_type, producer) {
+        (0x00, true) => Some(AcpiResource::MemoryWindow { base, len }),
+        (0x00, false) => Some(AcpiResource::Memory { base, len }),
+        (0x01, true) => Some(AcpiResource::IoWindow { base, len }),
+        (0x01, false) => Some(AcpiResource::Io {
+            base: base as u16,
+            len: len as u16,
+        }),
+        (0x02, _) => Some(AcpiResource::BusRange {
+            start: base as u16,
+            len: len as u16,
+        }),
+        _ => None,
+    })
+}
+
+/// Static AML data object (the subset used by named objects in generated tables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AmlValue {
+    Integer(u64),
+    Buffer(Vec<u8>),
+    Package(Vec<AmlValue>),
+    /// Strings and name references; their contents are not needed for resource decoding.
+    Other,
+}
+
+struct AmlReader<'a> {
+    aml: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AmlReader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.aml.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.aml.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    /// Reads a PkgLength and returns the absolute end offset of the package it delimits.
+    fn pkg_end(&mut self) -> Option<usize> {
+        let start = self.pos;
+        let lead = self.byte()?;
+        let follow = usize::from(lead >> 6);
+        let mut len = if follow == 0 {
+            usize::from(lead & 0x3F)
+        } else {
+            usize::from(lead & 0x0F)
+        };
+        for i in 0..follow {
+            len |= usize::from(self.byte()?) << (4 + 8 * i);
+        }
+        let end = start.checked_add(len)?;
+        (end <= self.aml.len()).then_some(end)
+    }
+
+    fn name_seg(&mut self) -> Option<String> {
+        let seg = self.bytes(4)?;
+        Some(String::from_utf8_lossy(seg).into_owned())
+    }
+
+    /// Reads a NameString, returned in ASL notation (`\_SB_.PCI0`, `^FOO_`, `BAR_`).
+    fn name_string(&mut self) -> Option<String> {
+        let mut out = String::new();
+        while let Some(&b) = self.aml.get(self.pos) {
+            if b != b'\\' && b != b'^' {
+                break;
+            }
+            out.push(b as char);
+            self.pos += 1;
+        }
+        let segs = match self.aml.get(self.pos)? {
+            0x00 => {
+                self.pos += 1;
+                0
+            }
+            0x2E => {
+                self.pos += 1;
+                2
+            }
+            0x2F => {
+                self.pos += 1;
+                usize::from(self.byte()?)
+            }
+            _ => 1,
+        };
+        for i in 0..segs {
+            if i != 0 {
+                out.push('.');
+            }
+            out.push_str(&self.name_seg()?);
+        }
+        Some(out)
+    }
+
+    fn data_object(&mut self) -> Option<AmlValue> {
+        let op = self.byte()?;
+        Some(match op {
+            0x00 => AmlValue::Integer(0),
+            0x01 => AmlValue::Integer(1),
+            0xFF => AmlValue::Integer(u64::MAX),
+            0x0A => AmlValue::Integer(u64::from(self.byte()?)),
+            0x0B => AmlValue::Integer(u64::from(u16::from_le_bytes(
+                self.bytes(2)?.try_into().ok()?,
+            ))),
+            0x0C => AmlValue::Integer(u64::from(u32::from_le_bytes(
+                self.bytes(4)?.try_into().ok()?,
+            ))),
+            0x0E => AmlValue::Integer(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?)),
+            0x0D => {
+                let nul = self.aml.get(self.pos..)?.iter().position(|&b| b == 0)?;
+                self.pos += nul + 1;
+                AmlValue::Other
+            }
+            0x11 => {
+                let end = self.pkg_end()?;
+                let AmlValue::Integer(size) = self.data_object()? else {
+                    return None;
+                };
+                let data = self.aml.get(self.pos..end)?;
+                self.pos = end;
+                let mut data = data.to_vec();
+                data.resize(usize::try_from(size).ok()?, 0);
+                AmlValue::Buffer(data)
+            }
+            0x12 => {
+                let end = self.pkg_end()?;
+                let count = self.byte()?;
+                let mut elements = Vec::with_capacity(usize::from(count));
+                while self.pos < end {
+                    elements.push(self.data_object()?);
+                }
+                AmlValue::Package(elements)
+            }
+            b'\\' | b'^' | b'_' | b'A'..=b'Z' | 0x2E | 0x2F => {
+                self.pos -= 1;
+                self.name_string()?;
+                AmlValue::Other
+            }
+            _ => return None,
+        })
+    }
+}
+
+fn join_path(scope: &str, name: &str) -> String {
+    if name.starts_with('\\') {
+        name.to_string()
+    } else if scope == "\\" {
+        format!("\\{name}")
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// Walks a term list, recording `_CRS`/`_PRT` objects and SystemIO operation regions.
+///
+/// Unknown opcodes end the walk of the current scope (but not of enclosing scopes), so a table
+/// using constructs outside the supported subset degrades to a partial result.
+fn walk_terms(aml: &[u8], end: usize, pos: usize, scope: &str, out: &mut AcpiResourceSummary) {
+    let mut r = AmlReader { aml, pos };
+    while r.pos < end {
+        let start = r.pos;
+        let Some(op) = r.byte() else {
+            return;
+        };
+        match op {
+            // ScopeOp
+            0x10 => {
+                let (Some(body_end), Some(name)) = (r.pkg_end(), r.name_string()) else {
+                    return;
+                };
+                walk_terms(aml, body_end, r.pos, &join_path(scope, &name), out);
+                r.pos = body_end;
+            }
+            // MethodOp: bodies are not evaluated.
+            0x14 => {
+                let Some(body_end) = r.pkg_end() else {
+                    return;
+                };
+                r.pos = body_end;
+            }
+            // NameOp
+            0x08 => {
+                let (Some(name), Some(value)) = (r.name_string(), r.data_object()) else {
+                    return;
+                };
+                record_named_object(scope, &name, value, out);
+            }
+            0x5B => {
+                let Some(ext) = r.byte() else {
+                    return;
+                };
+                match ext {
+                    // OpRegionOp: NameString, RegionSpace, RegionOffset, RegionLen.
+                    0x80 => {
+                        let Some(name) = r.name_string() else {
+                            return;
+                        };
+                        let (
+                            Some(space),
+                            Some(AmlValue::Integer(offset)),
+                            Some(AmlValue::Integer(len)),
+                        ) = (r.byte(), r.data_object(), r.data_object())
+                        else {
+                            return;
+                        };
+                        // Region space 1 = SystemIO.
+                        if space == 0x01 {
+                            out.claims.push(AcpiClaim {
+                                source: join_path(scope, &name),
+                                resource: AcpiResource::Io {
+                                    base: offset as u16,
+                                    len: len as u16,
+                                },
+                            });
+                        }
+                    }
+                    // FieldOp / ProcessorOp / PowerResOp / ThermalZoneOp: skip the package.
+                    0x81 | 0x83 | 0x84 | 0x85 => {
+                        let Some(body_end) = r.pkg_end() else {
+                            return;
+                        };
+                        r.pos = body_end;
+                    }
+                    // DeviceOp
+                    0x82 => {
+                        let (Some(body_end), Some(name)) = (r.pkg_end(), r.name_string()) else {
+                            return;
+                        };
+                        walk_terms(aml, body_end, r.pos, &join_path(scope, &name), out);
+                        r.pos = body_end;
+                    }
+                    _ => return,
+                }
+            }
+            _ => return,
+        }
+        debug_assert!(r.pos > start);
+    }
+}
+
+fn record_named_object(scope: &str, name: &str, value: AmlValue, out: &mut AcpiResourceSummary) {
+    match (name, value) {
+        ("_CRS", AmlValue::Buffer(template)) => {
+            // A malformed template still contributes the descriptors decoded before the error.
+            let resources = match parse_resource_template(&template) {
+                Ok(resources) => resources,
+                Err(_) => return,
+            };
+            let source = join_path(scope, "_CRS");
+            out.claims
+                .extend(resources.into_iter().map(|resource| AcpiClaim {
+                    source: source.clone(),
+                    resource,
+                }));
+        }
+        ("_PRT", AmlValue::Package(entries)) => {
+            for entry in entries {
+                let AmlValue::Package(fields) = entry else {
+                    continue;
+                };
+                // Package { Address, Pin, Source, SourceIndex }; only direct GSI entries
+                // (Source = Zero) are decoded.
+                if let [AmlValue::Integer(addr), AmlValue::Integer(pin), AmlValue::Integer(0), AmlValue::Integer(gsi)] =
+                    fields.as_slice()
+                {
+                    out.prt.push(AcpiPrtEntry {
+                        device: (addr >> 16) as u8,
+                        pin: *pin as u8,
+                        gsi: *gsi as u32,
+                    });
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Decodes the `_CRS`, `_PRT` and SystemIO operation regions of a DSDT/SSDT (including its SDT
+/// header).
+pub fn parse_dsdt_resources(table: &[u8]) -> Result<AcpiResourceSummary, AcpiParseError> {
+    let len = table_len(table, "DSDT")?;
+    let mut out = AcpiResourceSummary::default();
+    walk_terms(&table[..len], len, 36, "\\", &mut out);
+    Ok(out)
+}
+
+fn table_len(table: &[u8], what: &'static str) -> Result<usize, AcpiParseError> {
+    let len = u32_at(table, 4).ok_or(AcpiParseError::Truncated(what))? as usize;
+    if len < 36 || table.len() < len {
+        return Err(AcpiParseError::Truncated(what));
+    }
+    Ok(len)
+}
+
+/// Decodes the fixed-hardware I/O blocks, SCI and reset register from a FADT.
+pub fn parse_fadt_resources(
+    fadt: &[u8],
+    out: &mut AcpiResourceSummary,
+) -> Result<(), AcpiParseError> {
+    let len = table_len(fadt, "FADT")?;
+    let fadt = &fadt[..len];
+    const TRUNCATED: AcpiParseError = AcpiParseError::Truncated("FADT");
+
+    out.sci_irq = Some(u32::from(u16_at(fadt, 46).ok_or(TRUNCATED)?));
+
+    let mut io = |source: &str, base: u32, len: u16| {
+        if base != 0 && len != 0 {
+            out.claims.push(AcpiClaim {
+                source: format!("FADT.{source}"),
+                resource: AcpiResource::Io {
+                    base: base as u16,
+                    len,
+                },
+            });
+        }
+    };
+    io("SMI_CMD", u32_at(fadt, 48).ok_or(TRUNCATED)?, 1);
+    // (block address offset, length offset) pairs.
+    for (name, addr_off, len_off) in [
+        ("PM1a_EVT_BLK", 56, 88),
+        ("PM1b_EVT_BLK", 60, 88),
+        ("PM1a_CNT_BLK", 64, 89),
+        ("PM1b_CNT_BLK", 68, 89),
+        ("PM2_CNT_BLK", 72, 90),
+        ("PM_TMR_BLK", 76, 91),
+        ("GPE0_BLK", 80, 92),
+        ("GPE1_BLK", 84, 93),
+    ] {
+        let base = u32_at(fadt, addr_off).ok_or(TRUNCATED)?;
+        let len = *fadt.get(len_off).ok_or(TRUNCATED)?;
+        io(name, base, u16::from(len));
+    }
+
+    // RESET_REG is only meaningful when FADT.Flags advertises RESET_REG_SUP.
+    let flags = u32_at(fadt, 112).unwrap_or(0);
+    if flags & crate::FADT_FLAG_RESET_REG_SUP != 0 {
+        let space = *fadt.get(116).ok_or(TRUNCATED)?;
+        let addr = u64_at(fadt, 120).ok_or(TRUNCATED)?;
+        let resource = match space {
+            0x00 => AcpiResource::Memory { base: addr, len: 1 },
+            0x01 => AcpiResource::Io {
+                base: addr as u16,
+                len: 1,
+            },
+            _ => return Ok(()),
+        };
+        out.claims.push(AcpiClaim {
+            source: "FADT.RESET_REG".to_string(),
+            resource,
+        });
+    }
+    Ok(())
+}
+
+/// Size reserved for each local APIC / I/O APIC register window reported by the MADT.
+const APIC_WINDOW_SIZE: u64 = 0x1000;
+
+/// Decodes the local APIC and I/O APIC register windows from a MADT.
+pub fn parse_madt_resources(
+    madt: &[u8],
+    out: &mut AcpiResourceSummary,
+) -> Result<(), AcpiParseError> {
+    let len = table_len(madt, "MADT")?;
+    let madt = &madt[..len];
+    let lapic = u32_at(madt, 36).ok_or(AcpiParseError::Truncated("MADT"))?;
+    out.claims.push(AcpiClaim {
+        source: "MADT.LocalApicAddress".to_string(),
+        resource: AcpiResource::Memory {
+            base: u64::from(lapic),
+            len: APIC_WINDOW_SIZE,
+        },
+    });
+
+    let mut pos = 44;
+    while pos + 2 <= len {
+        let (kind, entry_len) = (madt[pos], usize::from(madt[pos + 1]));
+        if entry_len < 2 || pos + entry_len > len {
+            return Err(AcpiParseError::Truncated("MADT entry"));
+        }
+        // Type 1: I/O APIC { id, reserved, address u32, gsi base u32 }.
+        if kind == 1 {
+            let addr = u32_at(madt, pos + 4).ok_or(AcpiParseError::Truncated("MADT entry"))?;
+            out.claims.push(AcpiClaim {
+                source: "MADT.IoApic".to_string(),
+                resource: AcpiResource::Memory {
+                    base: u64::from(addr),
+                    len: APIC_WINDOW_SIZE,
+                },
+            });
+        }
+        pos += entry_len;
+    }
+    Ok(())
+}
+
+/// Size of the HPET register block (one timer block, per the IA-PC HPET specification).
+const HPET_BLOCK_SIZE: u64 = 0x400;
+
+/// Decodes the register block from an HPET description table.
+pub fn parse_hpet_resources(
+    hpet: &[u8],
+    out: &mut AcpiResourceSummary,
+) -> Result<(), AcpiParseError> {
+    let len = table_len(hpet, "HPET")?;
+    // Base Address GAS at offset 40; the 64-bit address lives at offset 44.
+    let base = u64_at(&hpet[..len], 44).ok_or(AcpiParseError::Truncated("HPET"))?;
+    out.claims.push(AcpiClaim {
+        source: "HPET.BaseAddress".to_string(),
+        resource: AcpiResource::Memory {
+            base,
+            len: HPET_BLOCK_SIZE,
+        },
+    });
+    Ok(())
+}
+
+/// Decodes the ECAM windows from an MCFG table.
+pub fn parse_mcfg_resources(
+    mcfg: &[u8],
+    out: &mut AcpiResourceSummary,
+) -> Result<(), AcpiParseError> {
+    let len = table_len(mcfg, "MCFG")?;
+    let mcfg = &mcfg[..len];
+    let mut pos = 44;
+    while pos + 16 <= len {
+        let base = u64_at(mcfg, pos).ok_or(AcpiParseError::Truncated("MCFG"))?;
+        let (start_bus, end_bus) = (mcfg[pos + 10], mcfg[pos + 11]);
+        let buses = u64::from(end_bus.saturating_sub(start_bus)) + 1;
+        out.claims.push(AcpiClaim {
+            source: "MCFG".to_string(),
+            resource: AcpiResource::Memory {
+                base: base + (u64::from(start_bus) << 20),
+                len: buses << 20,
+            },
+        });
+        pos += 16;
+    }
+    Ok(())
+}
+
+/// Reads the table set rooted at the RSDP at `rsdp_addr` through `read` (a physical memory
+/// reader) and decodes every resource it advertises.
+///
+/// Tables are located via the XSDT when the RSDP provides one, otherwise via the RSDT. Tables
+/// other than FADT/DSDT/SSDT/MADT/HPET/MCFG are ignored.
+pub fn read_acpi_resources(
+    rsdp_addr: u64,
+    mut read: impl FnMut(u64, &mut [u8]),
+) -> Result<AcpiResourceSummary, AcpiParseError> {
+    let mut rsdp = [0u8; 36];
+    read(rsdp_addr, &mut rsdp);
+    if &rsdp[..8] != b"RSD PTR " {
+        return Err(AcpiParseError::BadSignature {
+            expected: "RSDP",
+            found: rsdp[..4].try_into().unwrap_or_default(),
+        });
+    }
+
+    let mut read_table = |addr: u64| -> Result<Vec<u8>, AcpiParseError> {
+        let mut header = [0u8; 36];
+        read(addr, &mut header);
+        let len = u32_at(&header, 4).unwrap_or(0) as usize;
+        // Cap the read so a corrupt length cannot trigger a huge allocation.
+        if !(36..=0x10_0000).contains(&len) {
+            return Err(AcpiParseError::Truncated("table header"));
+        }
+        let mut table = vec![0u8; len];
+        read(addr, &mut table);
+        Ok(table)
+    };
+
+    let rsdt_addr = u64::from(u32_at(&rsdp, 16).unwrap_or(0));
+    let xsdt_addr = if rsdp[15] >= 2 {
+        u64_at(&rsdp, 24).unwrap_or(0)
+    } else {
+        0
+    };
+    let (root_addr, entry_size) = match (xsdt_addr, rsdt_addr) {
+        (0, 0) => return Err(AcpiParseError::NoRootTable),
+        (0, rsdt) => (rsdt, 4),
+        (xsdt, _) => (xsdt, 8),
+    };
+    let root = read_table(root_addr)?;
+    let table_addrs: Vec<u64> = root[36..]
+        .chunks_exact(entry_size)
+        .map(|entry| match entry_size {
+            4 => u64::from(u32::from_le_bytes(entry.try_into().unwrap())),
+            _ => u64::from_le_bytes(entry.try_into().unwrap()),
+        })
+        .collect();
+
+    fn merge(out: &mut AcpiResourceSummary, tables: AcpiResourceSummary) {
+        out.claims.extend(tables.claims);
+        out.prt.extend(tables.prt);
+    }
+
+    let mut out = AcpiResourceSummary::default();
+    for addr in table_addrs {
+        let table = read_table(addr)?;
+        match &table[..4] {
+            b"FACP" => {
+                parse_fadt_resources(&table, &mut out)?;
+                // X_DSDT (ACPI 2.0+) takes precedence over the 32-bit DSDT pointer.
+                let dsdt_addr = match u64_at(&table, 140) {
+                    Some(x_dsdt) if x_dsdt != 0 => x_dsdt,
+                    _ => u64::from(u32_at(&table, 40).unwrap_or(0)),
+                };
+                if dsdt_addr != 0 {
+                    let dsdt = read_table(dsdt_addr)?;
+                    merge(&mut out, parse_dsdt_resources(&dsdt)?);
+                }
+            }
+            b"SSDT" => merge(&mut out, parse_dsdt_resources(&table)?),
+            b"APIC" => parse_madt_resources(&table, &mut out)?,
+            b"HPET" => parse_hpet_resources(&table, &mut out)?,
+            b"MCFG" => parse_mcfg_resources(&table, &mut out)?,
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AcpiConfig, AcpiPlacement, AcpiTables};
+
+    fn claims_from<'a>(
+        summary: &'a AcpiResourceSummary,
+        source: &'a str,
+    ) -> impl Iterator<Item = AcpiResource> + 'a {
+        summary
+            .claims
+            .iter()
+            .filter(move |c| c.source == source)
+            .map(|c| c.resource)
+    }
+
+    #[test]
+    fn parses_small_and_large_descriptors() {
+        let template = [
+            0x47, 0x01, 0x70, 0x00, 0x70, 0x00, 0x01, 0x02, // IO 0x70/2
+            0x22, 0x00, 0x01, // IRQ {8}
+            0x2A, 0x04, 0x00, // DMA {2}
+            0x86, 0x09, 0x00, 0x01, 0x00, 0x00, 0xD0, 0xFE, 0x00, 0x04, 0x00,
+            0x00, // Mem32Fixed
+            0x79, 0x00,
+        ];
+        assert_eq!(
+            parse_resource_template(&template).unwrap(),
+            vec![
+                AcpiResource::Io { base: 0x70, len: 2 },
+                AcpiResource::Irq(8),
+                AcpiResource::Dma(2),
+                AcpiResource::Memory {
+                    base: 0xFED0_0000,
+                    len: 0x400
+                },
+            ]
+        );
+        assert_eq!(
+            parse_resource_template(&template[..8]),
+            Err(AcpiParseError::Truncated("resource template"))
+        );
+    }
+
+    #[test]
+    fn decodes_generated_dsdt() {
+        let cfg = AcpiConfig {
+            pcie_ecam_base: 0xB000_0000,
+            ..Default::default()
+        };
+        let tables = AcpiTables::build(&cfg, AcpiPlacement::default());
+        let summary = parse_dsdt_resources(&tables.dsdt).unwrap();
+
+        assert_eq!(
+            claims_from(&summary, "\\_SB_.RTC_._CRS").collect::<Vec<_>>(),
+            vec![
+                AcpiResource::Io { base: 0x70, len: 2 },
+                AcpiResource::Irq(8)
+            ]
+        );
+        assert_eq!(
+            claims_from(&summary, "\\_SB_.HPET._CRS").collect::<Vec<_>>(),
+            vec![AcpiResource::Memory {
+                base: 0xFED0_0000,
+                len: 0x400
+            }]
+        );
+        assert_eq!(
+            claims_from(&summary, "\\IMCR").collect::<Vec<_>>(),
+            vec![AcpiResource::Io { base: 0x22, len: 2 }]
+        );
+        assert!(claims_from(&summary, "\\_SB_.PCI0._CRS").any(|r| r
+            == AcpiResource::IoWindow {
+                base: 0x0D00,
+                len: 0xF300
+            }));
+        assert!(claims_from(&summary, "\\_SB_.PCI0._CRS").any(|r| r
+            == AcpiResource::MemoryWindow {
+                base: 0xC000_0000,
+                len: 0x3EC0_0000
+            }));
+
+        // 31 devices x 4 pins, swizzled onto PIRQA-D -> GSI 10-13.
+        assert_eq!(summary.prt.len(), 31 * 4);
+        assert!(summary.prt.contains(&AcpiPrtEntry {
+            device: 2,
+            pin: 0,
+            gsi: 12
+        }));
+    }
+
+    #[test]
+    fn reads_full_table_set_from_memory() {
+        let cfg = AcpiConfig {
+            pcie_ecam_base: 0xB000_0000,
+            ..Default::default()
+        };
+        let placement = AcpiPlacement::default();
+        let tables = AcpiTables::build(&cfg, placement);
+
+        let mut mem = vec![0u8; 0x10_0000 + 0x2_0000];
+        for (addr, bytes) in [
+            (tables.addresses.rsdp, &tables.rsdp),
+            (tables.addresses.rsdt, &tables.rsdt),
+            (tables.addresses.xsdt, &tables.xsdt),
+            (tables.addresses.fadt, &tables.fadt),
+            (tables.addresses.madt, &tables.madt),
+            (tables.addresses.hpet, &tables.hpet),
+            (tables.addresses.dsdt, &tables.dsdt),
+            (tables.addresses.facs, &tables.facs),
+        ]
+        .into_iter()
+        .chain(tables.addresses.mcfg.zip(tables.mcfg.as_ref()))
+        {
+            let addr = addr as usize;
+            mem[addr..addr + bytes.len()].copy_from_slice(bytes);
+        }
+
+        let summary = read_acpi_resources(tables.addresses.rsdp, |addr, buf| {
+            let addr = addr as usize;
+            buf.copy_from_slice(&mem[addr..addr + buf.len()]);
+        })
+        .unwrap();
+
+        assert_eq!(summary.sci_irq, Some(9));
+        assert_eq!(
+            claims_from(&summary, "FADT.PM1a_EVT_BLK").collect::<Vec<_>>(),
+            vec![AcpiResource::Io {
+                base: 0x400,
+                len: 4
+            }]
+        );
+        assert_eq!(
+            claims_from(&summary, "FADT.RESET_REG").collect::<Vec<_>>(),
+            vec![AcpiResource::Io {
+                base: 0xCF9,
+                len: 1
+            }]
+        );
+        assert_eq!(
+            claims_from(&summary, "MADT.IoApic").collect::<Vec<_>>(),
+            vec![AcpiResource::Memory {
+                base: 0xFEC0_0000,
+                len: 0x1000
+            }]
+        );
+        assert_eq!(
+            claims_from(&summary, "MCFG").collect::<Vec<_>>(),
+            vec![AcpiResource::Memory {
+                base: 0xB000_0000,
+                len: 256 << 20
+            }]
+        );
+        assert_eq!(summary.prt.len(), 31 * 4);
+    }
+}
@@ -17,6 +17,20 @@ pub const FADT_FLAG_SLP_BUTTON: u32 = 1 << 5; // bit 5: SLP_BUTTON (fixed-featur
 pub const FADT_FLAG_FIX_RTC: u32 = 1 << 6; // bit 6: FIX_RTC (RTC is a fixed hardware feature)
 pub const FADT_FLAG_RESET_REG_SUP: u32 = 1 << 10; // bit 10: RESET_REG_SUP (ResetReg/ResetValue supported)
 
+// GPE0 numbers reserved for Aero event sources.
+//
+// The DSDT `\_GPE` scope has an edge-style `_Exx` handler for each (the PM device latches the
+// status bit until the OSPM clears it), which Notifies the owning device object:
+// - `_E01`: `Notify (\_SB.PCI0, 0x00)` (Bus Check: rescan for hotplugged/removed functions).
+// - `_E02`: `Notify (\_SB.BAT0, 0x80)` (battery status change).
+// - `_E03`: `Notify (\_SB.LID0, 0x80)` (lid state change).
+//
+// GPE 0 is left unused. `BAT0`/`LID0` are declared not present (`_STA=0`) until a battery or lid
+// model reports through them, so their notifications are ignored by the OSPM.
+pub const AERO_GPE_PCI_HOTPLUG: u8 = 1;
+pub const AERO_GPE_BATTERY: u8 = 2;
+pub const AERO_GPE_LID: u8 = 3;
+
 /// Physical memory writing abstraction used by firmware to place tables in
 /// guest RAM.
 pub trait PhysicalMemory {
@@ -52,6 +66,17 @@ impl Default for AcpiPlacement {
     }
 }
 
+/// One NUMA proximity domain described by the `SRAT` and `SLIT` tables.
+///
+/// The node's index in [`AcpiConfig::numa_nodes`] is its proximity domain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AcpiNumaNode {
+    /// Local APIC IDs of the processors belonging to this node.
+    pub apic_ids: Vec<u8>,
+    /// Guest-physical `(base, length)` memory ranges belonging to this node.
+    pub memory: Vec<(u64, u64)>,
+}
+
 #[derive(Clone, Debug)]
 pub struct AcpiConfig {
     pub oem_id: [u8; 6],
@@ -64,6 +89,10 @@ pub struct AcpiConfig {
 
     pub local_apic_addr: u32,
     pub io_apic_addr: u32,
+    /// Base address of a second I/O APIC (ID 1) serving GSIs 24-47.
+    ///
+    /// Set to 0 (the default) to describe only the I/O APIC at `io_apic_addr`.
+    pub second_io_apic_addr: u32,
     pub hpet_addr: u64,
 
     /// ACPI SCI interrupt (legacy IRQ number).
@@ -109,11 +138,26 @@ pub struct AcpiConfig {
     /// Last bus number covered by the ECAM region.
     pub pcie_end_bus: u8,
 
+    /// Base physical address of a TPM 2.0 Command Response Buffer (CRB) register window.
+    ///
+    /// When set to a non-zero value, [`AcpiTables::build`] will emit a `TPM2`
+    /// table and a `MSFT0101` device in the DSDT describing the locality 0 CRB
+    /// interface at this address (conventionally `0xFED4_0000`).
+    ///
+    /// Set to 0 (the default) to omit both.
+    pub tpm_crb_base: u64,
+
     /// Mapping of PCI PIRQ[A-D] to platform GSIs (used by the DSDT `_PRT`).
     ///
     /// The swizzle follows: `pirq = (device + pin) mod 4` where `pin` is
     /// 0 for INTA#, 1 for INTB#, etc.
     pub pirq_to_gsi: [u32; 4],
+
+    /// NUMA topology published via `SRAT` (processor/memory affinity) and
+    /// `SLIT` (distance matrix).
+    ///
+    /// Leave empty (the default) to omit both tables.
+    pub numa_nodes: Vec<AcpiNumaNode>,
 }
 
 impl Default for AcpiConfig {
@@ -129,6 +173,7 @@ impl Default for AcpiConfig {
 
             local_apic_addr: 0xFEE0_0000,
             io_apic_addr: 0xFEC0_0000,
+            second_io_apic_addr: 0,
             hpet_addr: 0xFED0_0000,
 
             sci_irq: 9,
@@ -154,8 +199,12 @@ impl Default for AcpiConfig {
             pcie_start_bus: 0,
             pcie_end_bus: 0xFF,
 
+            tpm_crb_base: 0,
+
             // Match the default routing in `devices::pci::irq_router::PciIntxRouterConfig`.
             pirq_to_gsi: pci_routing::DEFAULT_PIRQ_TO_GSI,
+
+            numa_nodes: Vec::new(),
         }
     }
 }
@@ -169,6 +218,9 @@ pub struct AcpiAddresses {
     pub madt: u64,
     pub hpet: u64,
     pub mcfg: Option<u64>,
+    pub tpm2: Option<u64>,
+    pub srat: Option<u64>,
+    pub slit: Option<u64>,
     pub dsdt: u64,
     pub facs: u64,
 }
@@ -183,6 +235,9 @@ pub struct AcpiTables {
     pub madt: Vec<u8>,
     pub hpet: Vec<u8>,
     pub mcfg: Option<Vec<u8>>,
+    pub tpm2: Option<Vec<u8>>,
+    pub srat: Option<Vec<u8>>,
+    pub slit: Option<Vec<u8>>,
     pub dsdt: Vec<u8>,
     pub facs: Vec<u8>,
 }
@@ -198,6 +253,9 @@ impl fmt::Debug for AcpiTables {
             .field("madt_len", &self.madt.len())
             .field("hpet_len", &self.hpet.len())
             .field("mcfg_len", &self.mcfg.as_ref().map(|t| t.len()))
+            .field("tpm2_len", &self.tpm2.as_ref().map(|t| t.len()))
+            .field("srat_len", &self.srat.as_ref().map(|t| t.len()))
+            .field("slit_len", &self.slit.as_ref().map(|t| t.len()))
             .field("dsdt_len", &self.dsdt.len())
             .field("facs_len", &self.facs.len())
             .finish()
@@ -242,6 +300,27 @@ impl AcpiTables {
             (None, None)
         };
 
+        let (tpm2_addr, tpm2) = if cfg.tpm_crb_base != 0 {
+            let tpm2_addr = align_up(next, align);
+            let tpm2 = build_tpm2(cfg);
+            next = align_up(tpm2_addr + tpm2.len() as u64, align);
+            (Some(tpm2_addr), Some(tpm2))
+        } else {
+            (None, None)
+        };
+
+        let (srat_addr, srat, slit_addr, slit) = if !cfg.numa_nodes.is_empty() {
+            let srat_addr = align_up(next, align);
+            let srat = build_srat(cfg);
+            next = align_up(srat_addr + srat.len() as u64, align);
+            let slit_addr = align_up(next, align);
+            let slit = build_slit(cfg);
+            next = align_up(slit_addr + slit.len() as u64, align);
+            (Some(srat_addr), Some(srat), Some(slit_addr), Some(slit))
+        } else {
+            (None, None, None, None)
+        };
+
         let rsdt_addr = align_up(next, align);
         let fadt32: u32 = fadt_addr
             .try_into()
@@ -253,7 +332,10 @@ impl AcpiTables {
             .try_into()
             .expect("ACPI tables must be placed below 4GiB to populate the RSDT");
         let mut rsdt_entries = vec![fadt32, madt32, hpet32];
-        if let Some(addr) = mcfg_addr {
+        for addr in [mcfg_addr, tpm2_addr, srat_addr, slit_addr]
+            .into_iter()
+            .flatten()
+        {
             let addr32: u32 = addr
                 .try_into()
                 .expect("ACPI tables must be placed below 4GiB to populate the RSDT");
@@ -264,9 +346,11 @@ impl AcpiTables {
 
         let xsdt_addr = align_up(next, align);
         let mut xsdt_entries = vec![fadt_addr, madt_addr, hpet_addr];
-        if let Some(addr) = mcfg_addr {
-            xsdt_entries.push(addr);
-        }
+        xsdt_entries.extend(
+            [mcfg_addr, tpm2_addr, srat_addr, slit_addr]
+                .into_iter()
+                .flatten(),
+        );
         let xsdt = build_xsdt(cfg, &xsdt_entries);
         next = align_up(xsdt_addr + xsdt.len() as u64, align);
 
@@ -281,6 +365,9 @@ impl AcpiTables {
             madt: madt_addr,
             hpet: hpet_addr,
             mcfg: mcfg_addr,
+            tpm2: tpm2_addr,
+            srat: srat_addr,
+            slit: slit_addr,
             dsdt: dsdt_addr,
             facs: facs_addr,
         };
@@ -306,6 +393,9 @@ impl AcpiTables {
             madt,
             hpet,
             mcfg,
+            tpm2,
+            srat,
+            slit,
             dsdt,
             facs,
         }
@@ -320,6 +410,15 @@ impl AcpiTables {
         if let (Some(addr), Some(table)) = (self.addresses.mcfg, self.mcfg.as_ref()) {
             mem.write(addr, table);
         }
+        if let (Some(addr), Some(table)) = (self.addresses.tpm2, self.tpm2.as_ref()) {
+            mem.write(addr, table);
+        }
+        if let (Some(addr), Some(table)) = (self.addresses.srat, self.srat.as_ref()) {
+            mem.write(addr, table);
+        }
+        if let (Some(addr), Some(table)) = (self.addresses.slit, self.slit.as_ref()) {
+            mem.write(addr, table);
+        }
         mem.write(self.addresses.rsdt, &self.rsdt);
         mem.write(self.addresses.xsdt, &self.xsdt);
         mem.write(self.addresses.rsdp, &self.rsdp);
@@ -642,13 +741,11 @@ fn build_madt(cfg: &AcpiConfig) -> Vec<u8> {
         body.extend_from_slice(&1u32.to_le_bytes()); // flags: enabled
     }
 
-    // I/O APIC entry.
-    body.push(1); // type
-    body.push(12); // length
-    body.push(0); // IOAPIC ID
-    body.push(0); // reserved
-    body.extend_from_slice(&cfg.io_apic_addr.to_le_bytes());
-    body.extend_from_slice(&0u32.to_le_bytes()); // GSI base
+    // I/O APIC entries (24 redirection entries each).
+    body.extend_from_slice(&madt_io_apic(0, cfg.io_apic_addr, 0));
+    if cfg.second_io_apic_addr != 0 {
+        body.extend_from_slice(&madt_io_apic(1, cfg.second_io_apic_addr, 24));
+    }
 
     // Interrupt Source Override: ISA IRQ0 -> GSI2 (PIT).
     body.extend_from_slice(&madt_iso(
@@ -675,6 +772,16 @@ fn build_madt(cfg: &AcpiConfig) -> Vec<u8> {
     finalize_sdt(out)
 }
 
+fn madt_io_apic(id: u8, addr: u32, gsi_base: u32) -> [u8; 12] {
+    let mut out = [0u8; 12];
+    out[0] = 1;
+    out[1] = 12;
+    out[2] = id;
+    out[4..8].copy_from_slice(&addr.to_le_bytes());
+    out[8..12].copy_from_slice(&gsi_base.to_le_bytes());
+    out
+}
+
 fn madt_iso(bus: u8, source_irq: u8, gsi: u32, flags: u16) -> [u8; 10] {
     let mut out = [0u8; 10];
     out[0] = 2;
@@ -732,6 +839,98 @@ fn build_hpet(cfg: &AcpiConfig) -> Vec<u8> {
     finalize_sdt(out)
 }
 
+/// Offset of the CRB control area (`TPM_CRB_CTRL_REQ_0`) within the locality 0 register window.
+const TPM_CRB_CONTROL_AREA_OFFSET: u64 = 0x40;
+/// Size of the CRB register window covering localities 0-4.
+const TPM_CRB_WINDOW_SIZE: u32 = 0x5000;
+
+fn build_tpm2(cfg: &AcpiConfig) -> Vec<u8> {
+    // TCG ACPI Specification, "TPM2 Table" (revision 4).
+    let total_len = 64;
+    let mut out = Vec::with_capacity(total_len);
+    out.extend_from_slice(&build_sdt_header(*b"TPM2", 4, total_len as u32, cfg));
+
+    out.extend_from_slice(&0u16.to_le_bytes()); // platform class: client
+    out.extend_from_slice(&0u16.to_le_bytes()); // reserved
+    out.extend_from_slice(&(cfg.tpm_crb_base + TPM_CRB_CONTROL_AREA_OFFSET).to_le_bytes());
+    out.extend_from_slice(&7u32.to_le_bytes()); // start method: Command Response Buffer
+    out.extend_from_slice(&[0u8; 12]); // start method specific parameters (unused for CRB)
+
+    debug_assert_eq!(out.len(), total_len);
+    finalize_sdt(out)
+}
+
+/// `SLIT` distance between two nodes (ACPI normalizes the local distance to 10).
+const SLIT_LOCAL_DISTANCE: u8 = 10;
+const SLIT_REMOTE_DISTANCE: u8 = 20;
+
+fn build_srat(cfg: &AcpiConfig) -> Vec<u8> {
+    // SRAT revision 3 (ACPI 4.0+).
+    //
+    // Layout:
+    // - SDT header (36 bytes)
+    // - reserved (4 bytes, must be 1) + reserved (8 bytes)
+    // - Processor Local APIC Affinity structures (16 bytes each)
+    // - Memory Affinity structures (40 bytes each)
+    let mut body = Vec::new();
+    body.extend_from_slice(&1u32.to_le_bytes());
+    body.extend_from_slice(&[0u8; 8]);
+
+    for (domain, node) in cfg.numa_nodes.iter().enumerate() {
+        let domain = domain as u32;
+        for &apic_id in &node.apic_ids {
+            body.push(0); // type: Processor Local APIC Affinity
+            body.push(16); // length
+            body.push(domain as u8); // proximity domain [7:0]
+            body.push(apic_id);
+            body.extend_from_slice(&1u32.to_le_bytes()); // flags: enabled
+            body.push(0); // local SAPIC EID
+            body.extend_from_slice(&domain.to_le_bytes()[1..4]); // proximity domain [31:8]
+            body.extend_from_slice(&0u32.to_le_bytes()); // clock domain
+        }
+    }
+
+    for (domain, node) in cfg.numa_nodes.iter().enumerate() {
+        for &(base, len) in &node.memory {
+            body.push(1); // type: Memory Affinity
+            body.push(40); // length
+            body.extend_from_slice(&(domain as u32).to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes()); // reserved
+            body.extend_from_slice(&base.to_le_bytes());
+            body.extend_from_slice(&len.to_le_bytes());
+            body.extend_from_slice(&0u32.to_le_bytes()); // reserved
+            body.extend_from_slice(&1u32.to_le_bytes()); // flags: enabled
+            body.extend_from_slice(&[0u8; 8]); // reserved
+        }
+    }
+
+    let total_len = 36 + body.len();
+    let mut out = Vec::with_capacity(total_len);
+    out.extend_from_slice(&build_sdt_header(*b"SRAT", 3, total_len as u32, cfg));
+    out.extend_from_slice(&body);
+    finalize_sdt(out)
+}
+
+fn build_slit(cfg: &AcpiConfig) -> Vec<u8> {
+    let nodes = cfg.numa_nodes.len();
+    let total_len = 36 + 8 + nodes * nodes;
+    let mut out = Vec::with_capacity(total_len);
+    out.extend_from_slice(&build_sdt_header(*b"SLIT", 1, total_len as u32, cfg));
+    out.extend_from_slice(&(nodes as u64).to_le_bytes());
+    for from in 0..nodes {
+        for to in 0..nodes {
+            out.push(if from == to {
+                SLIT_LOCAL_DISTANCE
+            } else {
+                SLIT_REMOTE_DISTANCE
+            });
+        }
+    }
+
+    debug_assert_eq!(out.len(), total_len);
+    finalize_sdt(out)
+}
+
 fn build_dsdt(cfg: &AcpiConfig) -> Vec<u8> {
     let aml = build_dsdt_aml(cfg);
     let total_len = 36 + aml.len();
@@ -761,6 +960,7 @@ fn build_dsdt_aml(cfg: &AcpiConfig) -> Vec<u8> {
         aml_method_wak(),
         aml_scope_sb(cfg),
         aml_scope_pr(cfg),
+        aml_scope_gpe(),
         // Sleep state types for Win7: advertise common PC encodings.
         // Name (_S1_, Package () { 0x01, 0x01 })
         // Name (_S3_, Package () { 0x03, 0x03 })
@@ -795,15 +995,20 @@ fn aml_imcr_region_and_field() -> Vec<u8> {
 }
 
 fn aml_scope_sb(cfg: &AcpiConfig) -> Vec<u8> {
-    let sb_devices = [
+    let mut sb_devices = vec![
         aml_device_sys0(cfg),
         aml_device_pwrb(),
         aml_device_slpb(),
+        aml_device_lid0(),
+        aml_device_bat0(),
         aml_device_pci0(cfg),
         aml_device_hpet(cfg),
         aml_device_rtc(),
         aml_device_timr(),
     ];
+    if cfg.tpm_crb_base != 0 {
+        sb_devices.push(aml_device_tpm(cfg));
+    }
     let sb = sb_devices.concat();
     aml_scope(*b"_SB_", &sb)
 }
@@ -814,6 +1019,48 @@ fn aml_scope_pr(cfg: &AcpiConfig) -> Vec<u8> {
     aml_scope(*b"_PR_", &pr)
 }
 
+fn aml_scope_gpe() -> Vec<u8> {
+    // Scope (_GPE) { Method (_Exx, 0) { Notify (\_SB.<device>, <value>) } ... }
+    let gpe = [
+        aml_method_gpe_notify(AERO_GPE_PCI_HOTPLUG, *b"PCI0", 0x00),
+        aml_method_gpe_notify(AERO_GPE_BATTERY, *b"BAT0", 0x80),
+        aml_method_gpe_notify(AERO_GPE_LID, *b"LID0", 0x80),
+    ]
+    .concat();
+    aml_scope(*b"_GPE", &gpe)
+}
+
+fn aml_method_gpe_notify(gpe: u8, device: [u8; 4], value: u64) -> Vec<u8> {
+    // Method (_Exx, 0, NotSerialized) { Notify (\_SB.<device>, value) }
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let name = [
+        b'_',
+        b'E',
+        HEX[usize::from(gpe >> 4)],
+        HEX[usize::from(gpe & 0x0F)],
+    ];
+
+    let mut body = vec![
+        0x86,  // NotifyOp
+        b'\\', // RootChar
+        0x2E,  // DualNamePrefix
+    ];
+    body.extend_from_slice(b"_SB_");
+    body.extend_from_slice(&device);
+    body.extend_from_slice(&aml_integer(value));
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&name);
+    payload.push(0x00); // method flags: 0 arguments, NotSerialized, sync level 0
+    payload.extend_from_slice(&body);
+
+    let mut out = Vec::new();
+    out.push(0x14); // MethodOp
+    out.extend_from_slice(&aml_pkg_length_for_payload(payload.len()));
+    out.extend_from_slice(&payload);
+    out
+}
+
 fn aml_encode_pkg_length(len: usize) -> Vec<u8> {
     // Raw PkgLength value encoding (ACPI spec).
     //
@@ -1194,6 +1441,17 @@ fn aml_device_hpet(cfg: &AcpiConfig) -> Vec<u8> {
     aml_device(*b"HPET", &body)
 }
 
+fn aml_device_tpm(cfg: &AcpiConfig) -> Vec<u8> {
+    // `MSFT0101` is the ACPI ID both Windows and Linux bind their TPM 2.0 CRB
+    // drivers to; it is not an EISA ID, so it is published as a string.
+    let mut body = Vec::new();
+    body.extend_from_slice(&aml_name_string(*b"_HID", "MSFT0101"));
+    body.extend_from_slice(&aml_name_integer(*b"_UID", 0));
+    body.extend_from_slice(&aml_name_integer(*b"_STA", 0x0F));
+    body.extend_from_slice(&aml_name_buffer(*b"_CRS", &tpm_crs(cfg)));
+    aml_device(*b"TPM_", &body)
+}
+
 fn aml_device_rtc() -> Vec<u8> {
     // Matches typical PC/AT RTC resources (ports 0x70-0x71, IRQ8).
     let mut body = Vec::new();
@@ -1240,6 +1498,28 @@ fn aml_device_slpb() -> Vec<u8> {
     aml_device(*b"SLPB", &body)
 }
 
+fn aml_device_lid0() -> Vec<u8> {
+    // Lid device, the target of the lid GPE (`AERO_GPE_LID`).
+    //
+    // Not present (`_STA=0`) until a lid model backs it with `_LID`.
+    let mut body = Vec::new();
+    body.extend_from_slice(&aml_name_eisa_id(*b"_HID", "PNP0C0D"));
+    body.extend_from_slice(&aml_name_integer(*b"_UID", 0));
+    body.extend_from_slice(&aml_name_integer(*b"_STA", 0));
+    aml_device(*b"LID0", &body)
+}
+
+fn aml_device_bat0() -> Vec<u8> {
+    // Control-method battery, the target of the battery GPE (`AERO_GPE_BATTERY`).
+    //
+    // Not present (`_STA=0`) until a battery model backs it with `_BIF`/`_BST`.
+    let mut body = Vec::new();
+    body.extend_from_slice(&aml_name_eisa_id(*b"_HID", "PNP0C0A"));
+    body.extend_from_slice(&aml_name_integer(*b"_UID", 0));
+    body.extend_from_slice(&aml_name_integer(*b"_STA", 0));
+    aml_device(*b"BAT0", &body)
+}
+
 fn aml_name_eisa_id(name: [u8; 4], id: &str) -> Vec<u8> {
     let eisa = eisa_id_to_u32(id).expect("invalid EISA ID");
     let mut out = Vec::new();
@@ -1484,6 +1764,16 @@ fn hpet_crs(cfg: &AcpiConfig) -> Vec<u8> {
     out
 }
 
+fn tpm_crs(cfg: &AcpiConfig) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&memory32_fixed_descriptor(
+        cfg.tpm_crb_base as u32,
+        TPM_CRB_WINDOW_SIZE,
+    ));
+    out.extend_from_slice(&[0x79, 0x00]);
+    out
+}
+
 #[derive(Debug, Clone, Copy)]
 struct AddrSpaceDescriptorHeader {
     resource_type: u8,
@@ -1903,6 +2193,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dsdt_gpe_scope_notifies_reserved_gpe_targets() {
+        let cfg = AcpiConfig::default();
+        let placement = AcpiPlacement::default();
+        let tables = AcpiTables::build(&cfg, placement);
+        let aml = &tables.dsdt[36..];
+
+        // Method (_E01, 0) { Notify (\_SB.PCI0, Zero) }
+        let hotplug = [
+            &[0x14, 0x12][..], // MethodOp + pkglen
+            &b"_E01"[..],
+            &[0x00, 0x86, b'\\', 0x2E][..], // flags, NotifyOp, RootChar, DualNamePrefix
+            &b"_SB_PCI0"[..],
+            &[0x00][..],
+        ]
+        .concat();
+        assert!(
+            contains_subslice(aml, &hotplug),
+            "expected _GPE._E01 to Notify PCI0 with Bus Check"
+        );
+
+        for (gpe, device) in [(AERO_GPE_BATTERY, b"BAT0"), (AERO_GPE_LID, b"LID0")] {
+            let method = [
+                format!("_E0{gpe}").as_bytes(),
+                &[0x00, 0x86, b'\\', 0x2E][..],
+                &b"_SB_"[..],
+                &device[..],
+                &[0x0A, 0x80][..],
+            ]
+            .concat();
+            assert!(
+                contains_subslice(aml, &method),
+                "expected _GPE._E0{gpe} to Notify {}",
+                String::from_utf8_lossy(device)
+            );
+        }
+    }
+
     #[test]
     fn mcfg_emitted_with_expected_allocation_and_checksum() {
         let cfg = AcpiConfig {
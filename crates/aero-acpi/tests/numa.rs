@@ -0,0 +1,110 @@
+use aero_acpi::{AcpiConfig, AcpiNumaNode, AcpiPlacement, AcpiTables};
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+fn read_u32_le(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+fn read_u64_le(buf: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(buf[off..off + 8].try_into().unwrap())
+}
+
+#[test]
+fn srat_and_slit_are_omitted_by_default() {
+    let tables = AcpiTables::build(&AcpiConfig::default(), AcpiPlacement::default());
+    assert!(tables.srat.is_none());
+    assert!(tables.slit.is_none());
+    assert!(tables.addresses.srat.is_none());
+    assert!(tables.addresses.slit.is_none());
+}
+
+#[test]
+fn srat_and_slit_describe_numa_nodes() {
+    let cfg = AcpiConfig {
+        cpu_count: 3,
+        numa_nodes: vec![
+            AcpiNumaNode {
+                apic_ids: vec![0, 1],
+                memory: vec![(0, 0x0100_0000)],
+            },
+            AcpiNumaNode {
+                apic_ids: vec![2],
+                memory: vec![(0x0100_0000, 0x0200_0000), (0x1_0000_0000, 0x0100_0000)],
+            },
+        ],
+        ..Default::default()
+    };
+    let tables = AcpiTables::build(&cfg, AcpiPlacement::default());
+
+    // --- SRAT ---
+    let srat_addr = tables.addresses.srat.expect("SRAT should be present");
+    let srat = tables.srat.as_ref().unwrap();
+    assert_eq!(&srat[0..4], b"SRAT");
+    assert_eq!(read_u32_le(srat, 4) as usize, srat.len());
+    assert_eq!(srat[8], 3, "SRAT revision");
+    assert_eq!(checksum(srat), 0);
+    assert_eq!(read_u32_le(srat, 36), 1, "SRAT reserved field must be 1");
+
+    let mut cpus = Vec::new();
+    let mut memory = Vec::new();
+    let mut off = 48;
+    while off < srat.len() {
+        let len = srat[off + 1] as usize;
+        match srat[off] {
+            0 => {
+                assert_eq!(len, 16);
+                let domain = u32::from(srat[off + 2])
+                    | u32::from_le_bytes([0, srat[off + 9], srat[off + 10], srat[off + 11]]);
+                assert_eq!(read_u32_le(srat, off + 4) & 1, 1, "processor enabled");
+                cpus.push((srat[off + 3], domain));
+            }
+            1 => {
+                assert_eq!(len, 40);
+                assert_eq!(read_u32_le(srat, off + 28) & 1, 1, "memory enabled");
+                memory.push((
+                    read_u32_le(srat, off + 2),
+                    read_u64_le(srat, off + 8),
+                    read_u64_le(srat, off + 16),
+                ));
+            }
+            other => panic!("unexpected SRAT structure type {other}"),
+        }
+        off += len;
+    }
+    assert_eq!(off, srat.len());
+    assert_eq!(cpus, vec![(0, 0), (1, 0), (2, 1)]);
+    assert_eq!(
+        memory,
+        vec![
+            (0, 0, 0x0100_0000),
+            (1, 0x0100_0000, 0x0200_0000),
+            (1, 0x1_0000_0000, 0x0100_0000),
+        ]
+    );
+
+    // --- SLIT ---
+    let slit_addr = tables.addresses.slit.expect("SLIT should be present");
+    let slit = tables.slit.as_ref().unwrap();
+    assert_eq!(&slit[0..4], b"SLIT");
+    assert_eq!(read_u32_le(slit, 4) as usize, slit.len());
+    assert_eq!(checksum(slit), 0);
+    assert_eq!(read_u64_le(slit, 36), 2);
+    assert_eq!(&slit[44..], &[10, 20, 20, 10]);
+
+    // Both root tables reference them.
+    let xsdt_ptrs: Vec<u64> = tables.xsdt[36..]
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    assert!(xsdt_ptrs.contains(&srat_addr));
+    assert!(xsdt_ptrs.contains(&slit_addr));
+    let rsdt_ptrs: Vec<u64> = tables.rsdt[36..]
+        .chunks_exact(4)
+        .map(|c| u64::from(u32::from_le_bytes(c.try_into().unwrap())))
+        .collect();
+    assert!(rsdt_ptrs.contains(&srat_addr));
+    assert!(rsdt_ptrs.contains(&slit_addr));
+}
@@ -0,0 +1,79 @@
+use aero_acpi::{
+    parse_madt_resources, AcpiConfig, AcpiPlacement, AcpiResource, AcpiResourceSummary, AcpiTables,
+};
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+fn read_u32_le(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+/// Returns `(id, address, gsi_base)` for every MADT I/O APIC structure.
+fn madt_io_apics(madt: &[u8]) -> Vec<(u8, u32, u32)> {
+    let mut out = Vec::new();
+    let mut off = 44;
+    while off < madt.len() {
+        let len = madt[off + 1] as usize;
+        if madt[off] == 1 {
+            assert_eq!(len, 12);
+            out.push((
+                madt[off + 2],
+                read_u32_le(madt, off + 4),
+                read_u32_le(madt, off + 8),
+            ));
+        }
+        off += len;
+    }
+    assert_eq!(off, madt.len());
+    out
+}
+
+fn io_apic_claims(madt: &[u8]) -> Vec<AcpiResource> {
+    let mut summary = AcpiResourceSummary::default();
+    parse_madt_resources(madt, &mut summary).unwrap();
+    summary
+        .claims
+        .into_iter()
+        .filter(|claim| claim.source == "MADT.IoApic")
+        .map(|claim| claim.resource)
+        .collect()
+}
+
+#[test]
+fn madt_describes_one_io_apic_by_default() {
+    let tables = AcpiTables::build(&AcpiConfig::default(), AcpiPlacement::default());
+    assert_eq!(madt_io_apics(&tables.madt), vec![(0, 0xFEC0_0000, 0)]);
+}
+
+#[test]
+fn madt_describes_second_io_apic_after_the_first() {
+    let cfg = AcpiConfig {
+        second_io_apic_addr: 0xFEC0_1000,
+        ..Default::default()
+    };
+    let tables = AcpiTables::build(&cfg, AcpiPlacement::default());
+    let madt = &tables.madt;
+    assert_eq!(&madt[0..4], b"APIC");
+    assert_eq!(read_u32_le(madt, 4) as usize, madt.len());
+    assert_eq!(checksum(madt), 0);
+
+    assert_eq!(
+        madt_io_apics(madt),
+        vec![(0, 0xFEC0_0000, 0), (1, 0xFEC0_1000, 24)]
+    );
+    assert_eq!(
+        io_apic_claims(madt),
+        vec![
+            AcpiResource::Memory {
+                base: 0xFEC0_0000,
+                len: 0x1000,
+            },
+            AcpiResource::Memory {
+                base: 0xFEC0_1000,
+                len: 0x1000,
+            },
+        ]
+    );
+}
@@ -0,0 +1,72 @@
+use aero_acpi::{AcpiConfig, AcpiPlacement, AcpiTables};
+
+fn contains_subslice(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+#[test]
+fn tpm2_table_and_device_are_omitted_by_default() {
+    let tables = AcpiTables::build(&AcpiConfig::default(), AcpiPlacement::default());
+    assert!(tables.tpm2.is_none());
+    assert!(tables.addresses.tpm2.is_none());
+    assert!(!contains_subslice(&tables.dsdt[36..], b"MSFT0101"));
+}
+
+#[test]
+fn tpm2_table_describes_crb_control_area_when_enabled() {
+    let cfg = AcpiConfig {
+        tpm_crb_base: 0xFED4_0000,
+        ..Default::default()
+    };
+    let tables = AcpiTables::build(&cfg, AcpiPlacement::default());
+    let tpm2_addr = tables.addresses.tpm2.expect("TPM2 should be present");
+    let tpm2 = tables.tpm2.as_ref().unwrap();
+
+    assert_eq!(&tpm2[0..4], b"TPM2");
+    assert_eq!(
+        u32::from_le_bytes(tpm2[4..8].try_into().unwrap()) as usize,
+        tpm2.len()
+    );
+    assert_eq!(tpm2[8], 4, "TPM2 table revision");
+    assert_eq!(checksum(tpm2), 0);
+    // Platform class (client) + reserved.
+    assert_eq!(&tpm2[36..40], &[0, 0, 0, 0]);
+    // Address of the CRB control area (`TPM_CRB_CTRL_REQ_0`).
+    assert_eq!(
+        u64::from_le_bytes(tpm2[40..48].try_into().unwrap()),
+        0xFED4_0040
+    );
+    // Start method: Command Response Buffer.
+    assert_eq!(u32::from_le_bytes(tpm2[48..52].try_into().unwrap()), 7);
+
+    // Both root tables reference it.
+    let rsdt_ptrs: Vec<u64> = tables.rsdt[36..]
+        .chunks_exact(4)
+        .map(|c| u64::from(u32::from_le_bytes(c.try_into().unwrap())))
+        .collect();
+    assert!(rsdt_ptrs.contains(&tpm2_addr));
+    let xsdt_ptrs: Vec<u64> = tables.xsdt[36..]
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    assert!(xsdt_ptrs.contains(&tpm2_addr));
+
+    // The DSDT exposes a `MSFT0101` device whose `_CRS` covers the CRB register window:
+    // Memory32Fixed (ReadWrite, 0xFED40000, 0x5000).
+    let aml = &tables.dsdt[36..];
+    assert!(contains_subslice(aml, b"TPM_"));
+    assert!(contains_subslice(aml, b"MSFT0101\0"));
+    let crs = [
+        &[0x86, 0x09, 0x00, 0x01][..],
+        &0xFED4_0000u32.to_le_bytes()[..],
+        &0x5000u32.to_le_bytes()[..],
+    ]
+    .concat();
+    assert!(contains_subslice(aml, &crs));
+}
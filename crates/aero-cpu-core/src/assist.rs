@@ -1,5 +1,6 @@
 use aero_x86::{DecodedInst, Instruction, Mnemonic, OpKind, Register};
 
+use crate::assist_telemetry::AssistTelemetry;
 use crate::cpuid::{self, CpuFeatures};
 use crate::exception::{AssistReason, Exception};
 use crate::linear_mem::{
@@ -43,6 +44,11 @@ pub struct AssistContext {
     /// Number of `INVLPG` log entries dropped because [`AssistContext::invlpg_log`] hit
     /// [`AssistContext::INVLPG_LOG_CAP`].
     pub dropped_invlpg_log_entries: u64,
+    /// Number of Tier-0 assist exits taken by batch execution, indexed by
+    /// [`AssistReason::index`].
+    pub assist_exits: [u64; AssistReason::ALL.len()],
+    /// Unimplemented instructions, refused MSRs and all-zero `CPUID` leaves seen by Tier-0.
+    pub telemetry: AssistTelemetry,
 }
 
 impl AssistContext {
@@ -63,6 +69,11 @@ impl AssistContext {
         self.dropped_invlpg_log_entries = 0;
     }
 
+    #[inline]
+    pub(crate) fn record_assist_exit(&mut self, reason: AssistReason) {
+        self.assist_exits[reason.index()] = self.assist_exits[reason.index()].wrapping_add(1);
+    }
+
     #[inline]
     fn record_invlpg(&mut self, addr: u64) {
         if self.invlpg_log.len() < Self::INVLPG_LOG_CAP {
@@ -491,10 +502,13 @@ fn write_op_u16<B: CpuBus>(
 // CPUID
 // -------------------------------------------------------------------------------------------------
 
-fn instr_cpuid(ctx: &AssistContext, state: &mut CpuState) {
+fn instr_cpuid(ctx: &mut AssistContext, state: &mut CpuState) {
     let leaf = state.read_reg(Register::EAX) as u32;
     let subleaf = state.read_reg(Register::ECX) as u32;
     let res = cpuid::cpuid(&ctx.features, leaf, subleaf);
+    if res.eax | res.ebx | res.ecx | res.edx == 0 {
+        ctx.telemetry.record_zero_cpuid(leaf, subleaf);
+    }
     state.write_reg(Register::EAX, res.eax as u64);
     state.write_reg(Register::EBX, res.ebx as u64);
     state.write_reg(Register::ECX, res.ecx as u64);
@@ -506,7 +520,7 @@ fn instr_cpuid(ctx: &AssistContext, state: &mut CpuState) {
 // -------------------------------------------------------------------------------------------------
 
 fn msr_read(
-    _ctx: &AssistContext,
+    ctx: &AssistContext,
     time: &mut TimeSource,
     state: &mut CpuState,
     msr_index: u32,
@@ -517,7 +531,15 @@ fn msr_read(
             state.msr.tsc = tsc;
             Ok(tsc)
         }
-        _ => state.msr.read(msr_index),
+        _ => match ctx
+            .features
+            .hypervisor
+            .as_ref()
+            .and_then(|hv| hv.read_msr(msr_index))
+        {
+            Some(value) => Ok(value),
+            None => state.msr.read(msr_index),
+        },
     }
 }
 
@@ -558,13 +580,14 @@ fn msr_write(
 }
 
 fn instr_rdmsr(
-    ctx: &AssistContext,
+    ctx: &mut AssistContext,
     time: &mut TimeSource,
     state: &mut CpuState,
 ) -> Result<(), Exception> {
     require_cpl0(state)?;
     let msr_index = state.read_reg(Register::ECX) as u32;
-    let value = msr_read(ctx, time, state, msr_index)?;
+    let value = msr_read(ctx, time, state, msr_index)
+        .inspect_err(|_| ctx.telemetry.record_refused_msr(state, msr_index, None))?;
     state.write_reg(Register::EAX, value as u32 as u64);
     state.write_reg(Register::EDX, (value >> 32) as u32 as u64);
     Ok(())
@@ -580,7 +603,11 @@ fn instr_wrmsr(
     let eax = state.read_reg(Register::EAX) as u32 as u64;
     let edx = state.read_reg(Register::EDX) as u32 as u64;
     let value = (edx << 32) | eax;
-    msr_write(ctx, time, state, msr_index, value)?;
+    if let Err(e) = msr_write(ctx, time, state, msr_index, value) {
+        ctx.telemetry
+            .record_refused_msr(state, msr_index, Some(value));
+        return Err(e);
+    }
     Ok(())
 }
 
@@ -0,0 +1,181 @@
+//! Bounded record of guest behaviour the Tier-0 interpreter could not service.
+//!
+//! The collector lives in [`crate::assist::AssistContext::telemetry`] and is fed by the Tier-0
+//! batch runners and the assist layer:
+//! - instructions that fail with [`Exception::Unimplemented`] or `#UD` (either while decoding or
+//!   while executing, directly or via an assist), deduplicated by instruction bytes;
+//! - `RDMSR`/`WRMSR` of MSRs the CPU model refuses with `#GP`;
+//! - `CPUID` leaves that return all-zero registers.
+//!
+//! Every table is capped at [`ASSIST_TELEMETRY_CAP`] distinct entries; once full, hits on
+//! already-known entries are still counted but new entries are dropped and counted in
+//! [`AssistTelemetry::dropped`].
+
+use aero_x86::{DecodedInst, Mnemonic};
+
+use crate::exception::{AssistReason, Exception};
+use crate::state::{CpuMode, CpuState};
+
+/// Maximum number of distinct entries kept in each [`AssistTelemetry`] table.
+pub const ASSIST_TELEMETRY_CAP: usize = 256;
+
+/// An instruction Tier-0 could not execute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnimplementedSite {
+    /// RIP of the first occurrence.
+    pub rip: u64,
+    /// The 15 bytes fetched at `rip` for the first occurrence.
+    pub bytes: [u8; 15],
+    /// Length of the instruction within `bytes` (15 if it did not decode). The first `len` bytes
+    /// are the deduplication key.
+    pub len: u8,
+    pub mode: CpuMode,
+    /// Effective code size (16/32/64) the bytes were decoded with.
+    pub bitness: u32,
+    /// Decoded mnemonic, if the decoder recognised the instruction.
+    pub mnemonic: Option<Mnemonic>,
+    /// Assist class the instruction was routed through, if any.
+    pub assist: Option<AssistReason>,
+    pub exception: Exception,
+    pub hits: u64,
+}
+
+impl UnimplementedSite {
+    /// The instruction bytes used as the deduplication key.
+    pub fn instruction_bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+/// An MSR access refused with `#GP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefusedMsr {
+    pub msr: u32,
+    /// `true` for `WRMSR`, `false` for `RDMSR`.
+    pub write: bool,
+    /// Value of the most recent refused write.
+    pub last_value: Option<u64>,
+    /// RIP of the first occurrence.
+    pub rip: u64,
+    pub hits: u64,
+}
+
+/// A `CPUID` leaf/subleaf that returned zero in all four registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroCpuidLeaf {
+    pub leaf: u32,
+    pub subleaf: u32,
+    pub hits: u64,
+}
+
+/// Deduplicated, size-capped telemetry collected by the assist layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssistTelemetry {
+    pub unimplemented: Vec<UnimplementedSite>,
+    pub refused_msrs: Vec<RefusedMsr>,
+    pub zero_cpuid_leaves: Vec<ZeroCpuidLeaf>,
+    /// Number of new entries dropped because their table was full.
+    pub dropped: u64,
+}
+
+impl AssistTelemetry {
+    pub fn is_empty(&self) -> bool {
+        self.unimplemented.is_empty()
+            && self.refused_msrs.is_empty()
+            && self.zero_cpuid_leaves.is_empty()
+            && self.dropped == 0
+    }
+
+    /// Returns the collected telemetry and resets the collector.
+    pub fn take(&mut self) -> Self {
+        core::mem::take(self)
+    }
+
+    /// Record an instruction that faulted with `exception`. Exceptions other than
+    /// [`Exception::Unimplemented`] and `#UD` are ordinary guest faults and are ignored.
+    pub(crate) fn record_unimplemented(
+        &mut self,
+        state: &CpuState,
+        bytes: &[u8; 15],
+        decoded: Option<&DecodedInst>,
+        assist: Option<AssistReason>,
+        exception: &Exception,
+    ) {
+        if !matches!(
+            exception,
+            Exception::Unimplemented(_) | Exception::InvalidOpcode
+        ) {
+            return;
+        }
+        let len = decoded.map_or(15, |d| d.len.clamp(1, 15));
+        let key = &bytes[..usize::from(len)];
+        bump(
+            &mut self.unimplemented,
+            &mut self.dropped,
+            |site| site.instruction_bytes() == key,
+            |site| site.hits = site.hits.saturating_add(1),
+            || UnimplementedSite {
+                rip: state.rip(),
+                bytes: *bytes,
+                len,
+                mode: state.mode,
+                bitness: state.bitness(),
+                mnemonic: decoded.map(|d| d.instr.mnemonic()),
+                assist,
+                exception: exception.clone(),
+                hits: 1,
+            },
+        );
+    }
+
+    pub(crate) fn record_refused_msr(&mut self, state: &CpuState, msr: u32, write: Option<u64>) {
+        bump(
+            &mut self.refused_msrs,
+            &mut self.dropped,
+            |entry| entry.msr == msr && entry.write == write.is_some(),
+            |entry| {
+                entry.hits = entry.hits.saturating_add(1);
+                if write.is_some() {
+                    entry.last_value = write;
+                }
+            },
+            || RefusedMsr {
+                msr,
+                write: write.is_some(),
+                last_value: write,
+                rip: state.rip(),
+                hits: 1,
+            },
+        );
+    }
+
+    pub(crate) fn record_zero_cpuid(&mut self, leaf: u32, subleaf: u32) {
+        bump(
+            &mut self.zero_cpuid_leaves,
+            &mut self.dropped,
+            |entry| entry.leaf == leaf && entry.subleaf == subleaf,
+            |entry| entry.hits = entry.hits.saturating_add(1),
+            || ZeroCpuidLeaf {
+                leaf,
+                subleaf,
+                hits: 1,
+            },
+        );
+    }
+}
+
+fn bump<T>(
+    table: &mut Vec<T>,
+    dropped: &mut u64,
+    matches: impl Fn(&T) -> bool,
+    hit: impl FnOnce(&mut T),
+    new: impl FnOnce() -> T,
+) {
+    if let Some(entry) = table.iter_mut().find(|entry| matches(entry)) {
+        hit(entry);
+    } else if table.len() < ASSIST_TELEMETRY_CAP {
+        table.push(new());
+    } else {
+        *dropped = dropped.saturating_add(1);
+    }
+}
@@ -72,6 +72,48 @@ pub struct CpuFeatures {
 
     /// Topology information used by CPUID topology leaves.
     pub topology: CpuTopology,
+
+    /// Paravirtual hypervisor interface. When set, `CPUID.1:ECX[31]` (hypervisor present) is
+    /// reported as well.
+    pub hypervisor: Option<HypervisorInterface>,
+}
+
+/// A vendor block of CPUID leaves in the hypervisor range (`0x4000_0000..=0x4FFF_FFFF`) plus a
+/// run of read-only synthetic MSRs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HypervisorInterface {
+    /// Vendor leaf of the block. Its EAX reports the last leaf of the block and EBX/ECX/EDX the
+    /// signature.
+    pub base_leaf: u32,
+    /// Vendor signature, returned split across EBX/ECX/EDX of `base_leaf`.
+    pub signature: [u8; 12],
+    /// Leaves `base_leaf + 1..`, in order.
+    pub leaves: Vec<CpuidResult>,
+    /// First synthetic MSR.
+    pub msr_base: u32,
+    /// Values of MSRs `msr_base..`, in order. `WRMSR` to them raises `#GP`.
+    pub msrs: Vec<u64>,
+}
+
+impl HypervisorInterface {
+    /// Value of synthetic MSR `index`, if it belongs to this interface.
+    pub fn read_msr(&self, index: u32) -> Option<u64> {
+        let offset = index.checked_sub(self.msr_base)? as usize;
+        self.msrs.get(offset).copied()
+    }
+
+    fn cpuid(&self, leaf: u32) -> Option<CpuidResult> {
+        let index = leaf.checked_sub(self.base_leaf)? as usize;
+        if index == 0 {
+            return Some(CpuidResult {
+                eax: self.base_leaf + self.leaves.len() as u32,
+                ebx: pack_u32(self.signature[0..4].try_into().unwrap()),
+                ecx: pack_u32(self.signature[4..8].try_into().unwrap()),
+                edx: pack_u32(self.signature[8..12].try_into().unwrap()),
+            });
+        }
+        self.leaves.get(index - 1).copied()
+    }
 }
 
 impl CpuFeatures {
@@ -146,6 +188,7 @@ impl CpuFeatures {
             physical_address_bits: 48,
             linear_address_bits: 48,
             topology,
+            hypervisor: None,
         })
     }
 
@@ -379,7 +422,11 @@ pub fn cpuid(features: &CpuFeatures, leaf: u32, subleaf: u32) -> CpuidResult {
         0x0000_0001 => CpuidResult {
             eax: features.leaf1_eax,
             ebx: features.leaf1_ebx,
-            ecx: features.leaf1_ecx,
+            ecx: if features.hypervisor.is_some() {
+                features.leaf1_ecx | bits::LEAF1_ECX_HYPERVISOR
+            } else {
+                features.leaf1_ecx
+            },
             edx: features.leaf1_edx,
         },
         0x0000_0002 => CpuidResult {
@@ -454,6 +501,11 @@ pub fn cpuid(features: &CpuFeatures, leaf: u32, subleaf: u32) -> CpuidResult {
                 edx: 0,
             }
         }
+        0x4000_0000..=0x4FFF_FFFF => features
+            .hypervisor
+            .as_ref()
+            .and_then(|hv| hv.cpuid(leaf))
+            .unwrap_or(CpuidResult::ZERO),
         _ => CpuidResult::ZERO,
     }
 }
@@ -629,6 +681,8 @@ pub mod bits {
     pub const LEAF1_ECX_X2APIC: u32 = 1 << 21;
     pub const LEAF1_ECX_POPCNT: u32 = 1 << 23;
     pub const LEAF1_ECX_AES: u32 = 1 << 25;
+    /// Reserved on hardware; set by hypervisors to announce the `0x4000_0000` leaf range.
+    pub const LEAF1_ECX_HYPERVISOR: u32 = 1 << 31;
 
     // CPUID.80000001:EDX
     pub const EXT1_EDX_SYSCALL: u32 = 1 << 11;
@@ -645,7 +699,10 @@ pub mod bits {
 
 #[cfg(test)]
 mod tests {
-    use super::{bits, CpuFeatureOverrides, CpuFeatureSet, CpuFeatures, CpuProfile, CpuTopology};
+    use super::{
+        bits, cpuid, CpuFeatureOverrides, CpuFeatureSet, CpuFeatures, CpuProfile, CpuTopology,
+        CpuidResult, HypervisorInterface,
+    };
 
     #[test]
     fn leaf1_htt_bit_tracks_logical_count() {
@@ -680,4 +737,43 @@ mod tests {
         assert_ne!(dual.leaf1_edx & bits::LEAF1_EDX_HTT, 0);
         assert_eq!((dual.leaf1_ebx >> 16) & 0xFF, 2);
     }
+
+    #[test]
+    fn hypervisor_interface_is_reported_only_when_configured() {
+        let mut features = CpuFeatures::default();
+        assert_eq!(cpuid(&features, 1, 0).ecx & bits::LEAF1_ECX_HYPERVISOR, 0);
+        assert_eq!(cpuid(&features, 0x4000_0100, 0), CpuidResult::ZERO);
+
+        let info = CpuidResult {
+            eax: 1,
+            ebx: 2,
+            ecx: 3,
+            edx: 4,
+        };
+        features.hypervisor = Some(HypervisorInterface {
+            base_leaf: 0x4000_0100,
+            signature: *b"TestTestTest",
+            leaves: vec![info],
+            msr_base: 0x4000_0200,
+            msrs: vec![7],
+        });
+        assert_ne!(cpuid(&features, 1, 0).ecx & bits::LEAF1_ECX_HYPERVISOR, 0);
+        assert_eq!(features.feature_set().leaf1_ecx, features.leaf1_ecx);
+
+        let vendor = cpuid(&features, 0x4000_0100, 0);
+        assert_eq!(vendor.eax, 0x4000_0101);
+        let mut signature = Vec::new();
+        for reg in [vendor.ebx, vendor.ecx, vendor.edx] {
+            signature.extend_from_slice(&reg.to_le_bytes());
+        }
+        assert_eq!(signature, b"TestTestTest");
+        assert_eq!(cpuid(&features, 0x4000_0101, 0), info);
+        assert_eq!(cpuid(&features, 0x4000_0000, 0), CpuidResult::ZERO);
+        assert_eq!(cpuid(&features, 0x4000_0102, 0), CpuidResult::ZERO);
+
+        let hv = features.hypervisor.as_ref().unwrap();
+        assert_eq!(hv.read_msr(0x4000_0200), Some(7));
+        assert_eq!(hv.read_msr(0x4000_01FF), None);
+        assert_eq!(hv.read_msr(0x4000_0201), None);
+    }
 }
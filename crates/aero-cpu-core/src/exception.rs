@@ -100,3 +100,21 @@ pub enum AssistReason {
     Msr,
     Unsupported,
 }
+
+impl AssistReason {
+    /// Every assist reason, in [`AssistReason::index`] order.
+    pub const ALL: [AssistReason; 6] = [
+        AssistReason::Io,
+        AssistReason::Privileged,
+        AssistReason::Interrupt,
+        AssistReason::Cpuid,
+        AssistReason::Msr,
+        AssistReason::Unsupported,
+    ];
+
+    /// Dense index of this reason (`0..AssistReason::ALL.len()`), e.g. for per-reason counters.
+    #[inline]
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
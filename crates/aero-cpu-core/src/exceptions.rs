@@ -36,6 +36,35 @@ impl Exception {
         self as u8
     }
 
+    /// Map an IDT vector to the architecturally defined exception, if any.
+    ///
+    /// Reserved vectors (9, 15, 22..=31) and external interrupt vectors (>= 32) return `None`.
+    pub const fn from_vector(vector: u8) -> Option<Self> {
+        Some(match vector {
+            0 => Exception::DivideError,
+            1 => Exception::Debug,
+            2 => Exception::NonMaskableInterrupt,
+            3 => Exception::Breakpoint,
+            4 => Exception::Overflow,
+            5 => Exception::BoundRangeExceeded,
+            6 => Exception::InvalidOpcode,
+            7 => Exception::DeviceNotAvailable,
+            8 => Exception::DoubleFault,
+            10 => Exception::InvalidTss,
+            11 => Exception::SegmentNotPresent,
+            12 => Exception::StackFault,
+            13 => Exception::GeneralProtection,
+            14 => Exception::PageFault,
+            16 => Exception::X87Fpu,
+            17 => Exception::AlignmentCheck,
+            18 => Exception::MachineCheck,
+            19 => Exception::SimdFloatingPoint,
+            20 => Exception::Virtualization,
+            21 => Exception::ControlProtection,
+            _ => return None,
+        })
+    }
+
     /// Whether the CPU pushes an error code for this exception.
     #[inline]
     pub const fn pushes_error_code(self) -> bool {
@@ -358,6 +358,8 @@ pub fn run_batch_with_assists_with_config<B: CpuBus>(
             Ok(decoded) => decoded,
             Err(_) => {
                 let e = Exception::InvalidOpcode;
+                ctx.telemetry
+                    .record_unimplemented(&cpu.state, &bytes, None, None, &e);
                 cpu.state.apply_exception_side_effects(&e);
                 return BatchResult {
                     executed,
@@ -391,6 +393,8 @@ pub fn run_batch_with_assists_with_config<B: CpuBus>(
                         };
                     }
                 }
+                ctx.telemetry
+                    .record_unimplemented(&cpu.state, &bytes, Some(&decoded), None, &e);
                 cpu.state.apply_exception_side_effects(&e);
                 return BatchResult {
                     executed,
@@ -444,6 +448,7 @@ pub fn run_batch_with_assists_with_config<B: CpuBus>(
                 };
             }
             ExecOutcome::Assist(reason) => {
+                ctx.record_assist_exit(reason);
                 // Interrupt-related assists (CLI/STI/INT*/IRET*) require access to
                 // `interrupts::PendingEventState`, which is intentionally not part
                 // of the public `CpuState` ABI.
@@ -469,6 +474,13 @@ pub fn run_batch_with_assists_with_config<B: CpuBus>(
                     &decoded,
                     addr_size_override,
                 ) {
+                    ctx.telemetry.record_unimplemented(
+                        &cpu.state,
+                        &bytes,
+                        Some(&decoded),
+                        Some(reason),
+                        &e,
+                    );
                     return BatchResult {
                         executed,
                         exit: BatchExit::Exception(e),
@@ -592,6 +604,8 @@ pub fn run_batch_cpu_core_with_assists<B: CpuBus>(
             Ok(decoded) => decoded,
             Err(_) => {
                 let e = Exception::InvalidOpcode;
+                ctx.telemetry
+                    .record_unimplemented(&cpu.state, &bytes, None, None, &e);
                 cpu.state.apply_exception_side_effects(&e);
                 return BatchResult {
                     executed,
@@ -623,6 +637,8 @@ pub fn run_batch_cpu_core_with_assists<B: CpuBus>(
                         };
                     }
                 }
+                ctx.telemetry
+                    .record_unimplemented(&cpu.state, &bytes, Some(&decoded), None, &e);
                 cpu.state.apply_exception_side_effects(&e);
                 return BatchResult {
                     executed,
@@ -676,6 +692,7 @@ pub fn run_batch_cpu_core_with_assists<B: CpuBus>(
                 };
             }
             ExecOutcome::Assist(reason) => {
+                ctx.record_assist_exit(reason);
                 if reason == AssistReason::Interrupt {
                     let assist_outcome = match interrupts::exec_interrupt_assist_decoded(
                         cpu,
@@ -735,6 +752,13 @@ pub fn run_batch_cpu_core_with_assists<B: CpuBus>(
                     &decoded,
                     addr_size_override,
                 ) {
+                    ctx.telemetry.record_unimplemented(
+                        &cpu.state,
+                        &bytes,
+                        Some(&decoded),
+                        Some(reason),
+                        &e,
+                    );
                     return BatchResult {
                         executed,
                         exit: BatchExit::Exception(e),
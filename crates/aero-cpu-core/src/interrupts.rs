@@ -43,6 +43,17 @@ pub const MAX_EXTERNAL_INTERRUPTS: usize = 1024;
 /// [`CpuExit::TripleFault`].
 pub const MAX_INTERRUPT_FRAMES: usize = 1024;
 
+/// Number of exception deliveries remembered by [`PendingEventState::exception_history`].
+pub const EXCEPTION_HISTORY_LEN: usize = 8;
+
+/// One exception delivery attempt (see [`PendingEventState::exception_history`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExceptionRecord {
+    pub vector: u8,
+    /// RIP saved for the exception (the faulting instruction for faults).
+    pub rip: u64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CpuExit {
     /// Failure to deliver an exception (including #DF) that results in a reset.
@@ -269,6 +280,8 @@ pub struct PendingEventState {
     // --- Exception nesting / double fault escalation ---
     delivering_exception: Option<Exception>,
     exception_depth: u32,
+    /// Most recent exception delivery attempts, bounded by [`EXCEPTION_HISTORY_LEN`].
+    exception_history: VecDeque<ExceptionRecord>,
 
     // --- IRET bookkeeping ---
     interrupt_frames: Vec<InterruptFrame>,
@@ -361,6 +374,24 @@ impl PendingEventState {
         self.dropped_interrupt_frames
     }
 
+    /// The last [`EXCEPTION_HISTORY_LEN`] exception delivery attempts, oldest first.
+    ///
+    /// Exceptions raised while delivering another one are recorded as well, so after
+    /// [`CpuExit::TripleFault`] the history ends with the escalation that caused it.
+    pub fn exception_history(&self) -> &VecDeque<ExceptionRecord> {
+        &self.exception_history
+    }
+
+    fn record_exception(&mut self, exception: Exception, rip: u64) {
+        if self.exception_history.len() == EXCEPTION_HISTORY_LEN {
+            self.exception_history.pop_front();
+        }
+        self.exception_history.push_back(ExceptionRecord {
+            vector: exception.vector(),
+            rip,
+        });
+    }
+
     /// Inhibit maskable interrupts for exactly one instruction.
     ///
     /// This models the interrupt shadow after `STI` as well as `MOV SS`/`POP SS`
@@ -839,6 +870,7 @@ fn deliver_exception<B: CpuBus>(
     saved_rip: u64,
     error_code: Option<u32>,
 ) -> Result<(), CpuExit> {
+    pending.record_exception(exception, saved_rip);
     if let Some(first) = pending.delivering_exception {
         if first == Exception::DoubleFault {
             return Err(CpuExit::TripleFault);
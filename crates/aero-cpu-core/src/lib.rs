@@ -19,6 +19,7 @@ mod exception;
 mod fxsave;
 
 pub mod assist;
+pub mod assist_telemetry;
 pub mod cpuid;
 pub mod descriptors;
 pub mod exceptions;
@@ -141,6 +141,18 @@ impl<B, IO> PagingBus<B, IO> {
         }
     }
 
+    /// Physical address an instruction fetch from `vaddr` would use, without setting
+    /// accessed bits or raising faults. `None` if the fetch would fault.
+    #[inline]
+    pub fn probe_fetch_paddr(&mut self, vaddr: u64) -> Option<u64>
+    where
+        B: MemoryBus,
+    {
+        self.mmu
+            .translate_probe(&mut self.phys, vaddr, AccessType::Execute, self.cpl)
+            .ok()
+    }
+
     /// Translate a range without performing any guest-visible side effects.
     ///
     /// Returns `Ok(true)` when the entire range can be accessed, and `Ok(false)` when translation
@@ -1,4 +1,4 @@
-use aero_cpu_core::interrupts::{CpuCore, CpuExit, InterruptController};
+use aero_cpu_core::interrupts::{CpuCore, CpuExit, InterruptController, EXCEPTION_HISTORY_LEN};
 use aero_cpu_core::mem::{CpuBus, FlatTestBus};
 use aero_cpu_core::state::{gpr, CpuMode, RFLAGS_IF, RFLAGS_IOPL_MASK, SEG_ACCESS_PRESENT};
 use aero_x86::Register;
@@ -394,6 +394,45 @@ fn double_fault_delivery_failure_triggers_triple_fault() {
         cpu.deliver_pending_event(&mut mem),
         Err(CpuExit::TripleFault)
     );
+
+    // #PF, the nested #PF that escalates to #DF, #DF, and the #PF that fails its delivery.
+    let history: Vec<_> = cpu
+        .pending
+        .exception_history()
+        .iter()
+        .map(|r| (r.vector, r.rip))
+        .collect();
+    assert_eq!(
+        history,
+        [(14, 0x1234), (14, 0x1234), (8, 0x1234), (14, 0x1234)]
+    );
+}
+
+#[test]
+fn exception_history_keeps_only_the_most_recent_deliveries() -> Result<(), CpuExit> {
+    let mut mem = FlatTestBus::new(0x10000);
+    let mut cpu = CpuCore::new(CpuMode::Real);
+    cpu.state.write_reg(Register::SP, 0x8000);
+
+    for i in 0..EXCEPTION_HISTORY_LEN as u64 + 3 {
+        cpu.pending.raise_exception_fault(
+            &mut cpu.state,
+            aero_cpu_core::exceptions::Exception::InvalidOpcode,
+            0x100 + i,
+            None,
+            None,
+        );
+        cpu.deliver_pending_event(&mut mem)?;
+    }
+
+    let history = cpu.pending.exception_history();
+    assert_eq!(history.len(), EXCEPTION_HISTORY_LEN);
+    assert_eq!(history.front().map(|r| (r.vector, r.rip)), Some((6, 0x103)));
+    assert_eq!(
+        history.back().map(|r| (r.vector, r.rip)),
+        Some((6, 0x100 + EXCEPTION_HISTORY_LEN as u64 + 2))
+    );
+    Ok(())
 }
 
 #[test]
@@ -8,7 +8,7 @@ use aero_cpu_core::mem::FlatTestBus;
 use aero_cpu_core::state::CpuMode;
 use aero_cpu_core::CpuBus;
 use aero_cpu_core::CpuCore;
-use aero_cpu_core::Exception;
+use aero_cpu_core::{AssistReason, Exception};
 use aero_x86::Register;
 
 const BUS_SIZE: usize = 0x2000;
@@ -90,6 +90,7 @@ fn enabling_sse42_in_cpuid_allows_tier0_crc32() {
 
     let res = run_batch_with_assists(&mut ctx, &mut cpu, &mut bus, 64);
     assert_eq!(res.exit, BatchExit::Halted);
+    assert_eq!(ctx.assist_exits[AssistReason::Cpuid.index()], 1);
 
     let leaf1_ecx = bus.read_u32(CPUID_ECX_ADDR).unwrap();
     assert_ne!(leaf1_ecx & bits::LEAF1_ECX_SSE42, 0);
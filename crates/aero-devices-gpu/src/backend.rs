@@ -44,7 +44,7 @@ pub struct AeroGpuBackendCompletion {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AeroGpuBackendScanout {
     pub width: u32,
     pub height: u32,
@@ -18,7 +18,8 @@
 //!
 //! By default, the crate is GPU-free. Enable the `aerogpu-native` feature (or its compatibility
 //! alias `wgpu-backend`) to execute command streams in-process via a WGPU/WebGPU-backed executor
-//! (intended for native tests).
+//! (intended for native tests). [`software::SoftwareAeroGpuBackend`] executes the 2D subset of the
+//! command stream on the CPU for headless validation without a GPU.
 
 // The `aerogpu-native`/`wgpu-backend` feature enables a native in-process executor wrapper used by
 // host-side tests. It is not intended to be enabled for `wasm32` builds; the browser runtime uses
@@ -37,6 +38,7 @@ pub mod pci_device;
 pub mod regs;
 pub mod ring;
 pub mod scanout;
+pub mod software;
 pub mod vblank;
 
 #[cfg(all(feature = "aerogpu-native", not(target_arch = "wasm32")))]
@@ -62,3 +64,4 @@ pub use ring::{
     FENCE_PAGE_MAGIC_OFFSET, RING_HEAD_OFFSET, RING_TAIL_OFFSET,
 };
 pub use scanout::{AeroGpuCursorConfig, AeroGpuFormat, AeroGpuScanoutConfig};
+pub use software::SoftwareAeroGpuBackend;
@@ -0,0 +1,711 @@
+//! CPU-only AeroGPU command backend for headless validation.
+//!
+//! [`SoftwareAeroGpuBackend`] executes the subset of `AEROGPU_CMD` used by the WDDM driver's 2D
+//! path (resource creation/upload, guest-backed dirty ranges, buffer and texture copies, color
+//! clears and `PRESENT`) against resources stored in plain `Vec`s, and completes every fence
+//! synchronously. It needs no GPU, so CI can compare presented pixels deterministically.
+//!
+//! Validation is deliberately strict: malformed packets, out-of-range copies and 3D pipeline
+//! commands the backend does not implement stop execution of the submission and are reported via
+//! [`AeroGpuBackendCompletion::error`]. Nothing in the command stream can make the backend panic,
+//! which makes it usable as a protocol fuzz target.
+
+use std::collections::{HashMap, VecDeque};
+
+use aero_protocol::aerogpu::aerogpu_cmd::{
+    AerogpuCmdOpcode, AerogpuCmdPacket, AerogpuCmdStreamIter, AEROGPU_CLEAR_COLOR,
+    AEROGPU_COPY_FLAG_WRITEBACK_DST, AEROGPU_MAX_RENDER_TARGETS,
+};
+use aero_protocol::aerogpu::aerogpu_pci::AerogpuFormat;
+use aero_protocol::aerogpu::aerogpu_ring::{
+    AerogpuAllocEntry, AerogpuAllocTableHeader, AEROGPU_ALLOC_FLAG_READONLY,
+};
+use memory::MemoryBus;
+
+use crate::backend::{
+    AeroGpuBackendCompletion, AeroGpuBackendScanout, AeroGpuBackendSubmission,
+    AeroGpuCommandBackend,
+};
+
+/// Largest texture width/height accepted by `CREATE_TEXTURE2D`.
+pub const SOFTWARE_BACKEND_MAX_TEXTURE_DIMENSION: u32 = 16384;
+/// Upper bound on the bytes held by all live resources.
+pub const SOFTWARE_BACKEND_MAX_RESOURCE_BYTES: usize = 512 * 1024 * 1024;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Backing {
+    alloc_id: u32,
+    offset: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TextureDesc {
+    width: u32,
+    height: u32,
+    format: AerogpuFormat,
+    row_pitch: usize,
+}
+
+#[derive(Debug)]
+struct Resource {
+    /// `None` for buffers.
+    texture: Option<TextureDesc>,
+    /// Linear contents; textures use the guest layout (`row_pitch * height`).
+    data: Vec<u8>,
+    backing: Option<Backing>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Alloc {
+    gpa: u64,
+    size: u64,
+    read_only: bool,
+}
+
+/// Format classes that can be cleared, copied between and presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelOrder {
+    Bgra,
+    Rgba,
+}
+
+fn channel_order(format: AerogpuFormat) -> Option<(ChannelOrder, bool)> {
+    use AerogpuFormat::*;
+    match format {
+        B8G8R8A8Unorm | B8G8R8A8UnormSrgb => Some((ChannelOrder::Bgra, false)),
+        B8G8R8X8Unorm | B8G8R8X8UnormSrgb => Some((ChannelOrder::Bgra, true)),
+        R8G8B8A8Unorm | R8G8B8A8UnormSrgb => Some((ChannelOrder::Rgba, false)),
+        R8G8B8X8Unorm | R8G8B8X8UnormSrgb => Some((ChannelOrder::Rgba, true)),
+        _ => None,
+    }
+}
+
+/// Fixed-layout packet fields, read with the protocol's forward-compatible prefix rule (packets
+/// may be longer than the fields this backend understands).
+struct Fields<'a> {
+    name: &'static str,
+    payload: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(
+        packet: &AerogpuCmdPacket<'a>,
+        name: &'static str,
+        min_len: usize,
+    ) -> Result<Self, String> {
+        if packet.payload.len() < min_len {
+            return Err(format!(
+                "{name}: payload is {} bytes, expected at least {min_len}",
+                packet.payload.len()
+            ));
+        }
+        Ok(Self {
+            name,
+            payload: packet.payload,
+        })
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.payload[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.payload[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn err(&self, msg: impl std::fmt::Display) -> String {
+        format!("{}: {msg}", self.name)
+    }
+}
+
+fn checked_range(offset: u64, size: u64, len: usize) -> Option<std::ops::Range<usize>> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    (end <= len).then_some(start..end)
+}
+
+/// CPU-only backend implementing the 2D subset of the AeroGPU command stream.
+#[derive(Debug, Default)]
+pub struct SoftwareAeroGpuBackend {
+    resources: HashMap<u32, Resource>,
+    resource_bytes: usize,
+    render_target: u32,
+    presented: HashMap<u32, AeroGpuBackendScanout>,
+    completed: VecDeque<AeroGpuBackendCompletion>,
+}
+
+impl SoftwareAeroGpuBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read back a texture as tightly packed RGBA8 (`None` if `handle` is not a texture).
+    pub fn read_texture_rgba8(&self, handle: u32) -> Option<AeroGpuBackendScanout> {
+        let res = self.resources.get(&handle)?;
+        let desc = res.texture?;
+        Some(Self::texture_to_rgba8(desc, &res.data))
+    }
+
+    fn texture_to_rgba8(desc: TextureDesc, data: &[u8]) -> AeroGpuBackendScanout {
+        let (order, opaque) = channel_order(desc.format).expect("validated at creation");
+        let width = desc.width as usize;
+        let mut rgba8 = Vec::with_capacity(width * desc.height as usize * BYTES_PER_PIXEL);
+        for row in data.chunks_exact(desc.row_pitch) {
+            for px in row[..width * BYTES_PER_PIXEL].chunks_exact(BYTES_PER_PIXEL) {
+                let [r, g, b] = match order {
+                    ChannelOrder::Bgra => [px[2], px[1], px[0]],
+                    ChannelOrder::Rgba => [px[0], px[1], px[2]],
+                };
+                rgba8.extend_from_slice(&[r, g, b, if opaque { 0xFF } else { px[3] }]);
+            }
+        }
+        AeroGpuBackendScanout {
+            width: desc.width,
+            height: desc.height,
+            rgba8,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        submission: &AeroGpuBackendSubmission,
+    ) -> Result<(), String> {
+        if submission.cmd_stream.is_empty() {
+            return Ok(());
+        }
+        let allocs = match submission.alloc_table.as_deref() {
+            Some(bytes) => decode_alloc_table(bytes)?,
+            None => HashMap::new(),
+        };
+
+        let iter = AerogpuCmdStreamIter::new(&submission.cmd_stream)
+            .map_err(|err| format!("invalid command stream header: {err}"))?;
+        let mut offset = crate::cmd::CMD_STREAM_HEADER_SIZE_BYTES as usize;
+        for packet in iter {
+            let packet = packet.map_err(|err| format!("packet at offset {offset}: {err}"))?;
+            offset += packet.hdr.size_bytes as usize;
+            self.execute_packet(mem, &allocs, &packet)?;
+        }
+        Ok(())
+    }
+
+    fn execute_packet(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let Some(opcode) = packet.opcode else {
+            // Unknown opcodes are skipped so newer guests can append packets older hosts ignore.
+            return Ok(());
+        };
+        match opcode {
+            AerogpuCmdOpcode::Nop | AerogpuCmdOpcode::DebugMarker | AerogpuCmdOpcode::Flush => {
+                Ok(())
+            }
+            AerogpuCmdOpcode::CreateBuffer => self.create_buffer(mem, allocs, packet),
+            AerogpuCmdOpcode::CreateTexture2d => self.create_texture2d(mem, allocs, packet),
+            AerogpuCmdOpcode::DestroyResource => self.destroy_resource(packet),
+            AerogpuCmdOpcode::ResourceDirtyRange => self.resource_dirty_range(mem, allocs, packet),
+            AerogpuCmdOpcode::UploadResource => self.upload_resource(packet),
+            AerogpuCmdOpcode::CopyBuffer => self.copy_buffer(mem, allocs, packet),
+            AerogpuCmdOpcode::CopyTexture2d => self.copy_texture2d(mem, allocs, packet),
+            AerogpuCmdOpcode::SetRenderTargets => self.set_render_targets(packet),
+            AerogpuCmdOpcode::Clear => self.clear(packet),
+            AerogpuCmdOpcode::Present | AerogpuCmdOpcode::PresentEx => self.present(packet),
+            other => Err(format!(
+                "{other:?} is not supported by the software backend"
+            )),
+        }
+    }
+
+    fn insert_resource(
+        &mut self,
+        f: &Fields<'_>,
+        handle: u32,
+        res: Resource,
+    ) -> Result<(), String> {
+        if handle == 0 {
+            return Err(f.err("handle 0 is reserved"));
+        }
+        if self.resources.contains_key(&handle) {
+            return Err(f.err(format_args!("handle {handle} is still in use")));
+        }
+        self.resource_bytes += res.data.len();
+        self.resources.insert(handle, res);
+        Ok(())
+    }
+
+    fn reserve_bytes(&self, f: &Fields<'_>, len: usize) -> Result<Vec<u8>, String> {
+        if self
+            .resource_bytes
+            .checked_add(len)
+            .is_none_or(|total| total > SOFTWARE_BACKEND_MAX_RESOURCE_BYTES)
+        {
+            return Err(f.err(format_args!(
+                "{len} bytes would exceed the {SOFTWARE_BACKEND_MAX_RESOURCE_BYTES}-byte resource budget"
+            )));
+        }
+        Ok(vec![0u8; len])
+    }
+
+    fn create_buffer(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let f = Fields::new(packet, "CREATE_BUFFER", 24)?;
+        let handle = f.u32(0);
+        let size = usize::try_from(f.u64(8))
+            .ok()
+            .filter(|&size| size != 0)
+            .ok_or_else(|| f.err("size_bytes must be non-zero"))?;
+        let backing = (f.u32(16) != 0).then(|| Backing {
+            alloc_id: f.u32(16),
+            offset: u64::from(f.u32(20)),
+        });
+
+        let mut res = Resource {
+            texture: None,
+            data: self.reserve_bytes(&f, size)?,
+            backing,
+        };
+        if let Some(backing) = backing {
+            let gpa = backing_gpa(&f, allocs, backing, 0, size, false)?;
+            mem.read_physical(gpa, &mut res.data);
+        }
+        self.insert_resource(&f, handle, res)
+    }
+
+    fn create_texture2d(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let f = Fields::new(packet, "CREATE_TEXTURE2D", 40)?;
+        let handle = f.u32(0);
+        let format = AerogpuFormat::from_u32(f.u32(8))
+            .filter(|&format| channel_order(format).is_some())
+            .ok_or_else(|| f.err(format_args!("unsupported format {}", f.u32(8))))?;
+        let (width, height) = (f.u32(12), f.u32(16));
+        if width == 0
+            || height == 0
+            || width > SOFTWARE_BACKEND_MAX_TEXTURE_DIMENSION
+            || height > SOFTWARE_BACKEND_MAX_TEXTURE_DIMENSION
+        {
+            return Err(f.err(format_args!("invalid dimensions {width}x{height}")));
+        }
+        if f.u32(20) != 1 || f.u32(24) != 1 {
+            return Err(f.err(format_args!(
+                "only single-mip, single-layer textures are supported (mip_levels={} array_layers={})",
+                f.u32(20),
+                f.u32(24)
+            )));
+        }
+        let min_pitch = width as usize * BYTES_PER_PIXEL;
+        let row_pitch = match f.u32(28) as usize {
+            0 => min_pitch,
+            pitch if pitch >= min_pitch && pitch.is_multiple_of(BYTES_PER_PIXEL) => pitch,
+            pitch => {
+                return Err(f.err(format_args!(
+                    "row_pitch_bytes {pitch} is invalid for width {width}"
+                )))
+            }
+        };
+        let backing = (f.u32(32) != 0).then(|| Backing {
+            alloc_id: f.u32(32),
+            offset: u64::from(f.u32(36)),
+        });
+
+        let len = row_pitch * height as usize;
+        let mut res = Resource {
+            texture: Some(TextureDesc {
+                width,
+                height,
+                format,
+                row_pitch,
+            }),
+            data: self.reserve_bytes(&f, len)?,
+            backing,
+        };
+        if let Some(backing) = backing {
+            let gpa = backing_gpa(&f, allocs, backing, 0, len, false)?;
+            mem.read_physical(gpa, &mut res.data);
+        }
+        self.insert_resource(&f, handle, res)
+    }
+
+    fn destroy_resource(&mut self, packet: &AerogpuCmdPacket<'_>) -> Result<(), String> {
+        let f = Fields::new(packet, "DESTROY_RESOURCE", 4)?;
+        let handle = f.u32(0);
+        let res = self
+            .resources
+            .remove(&handle)
+            .ok_or_else(|| f.err(format_args!("unknown handle {handle}")))?;
+        self.resource_bytes -= res.data.len();
+        if self.render_target == handle {
+            self.render_target = 0;
+        }
+        Ok(())
+    }
+
+    fn resource_mut(&mut self, f: &Fields<'_>, handle: u32) -> Result<&mut Resource, String> {
+        self.resources
+            .get_mut(&handle)
+            .ok_or_else(|| f.err(format_args!("unknown handle {handle}")))
+    }
+
+    fn resource_dirty_range(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let f = Fields::new(packet, "RESOURCE_DIRTY_RANGE", 24)?;
+        let (handle, offset, size) = (f.u32(0), f.u64(8), f.u64(16));
+        let res = self.resource_mut(&f, handle)?;
+        let backing = res
+            .backing
+            .ok_or_else(|| f.err(format_args!("handle {handle} is not guest-backed")))?;
+        let range = checked_range(offset, size, res.data.len())
+            .ok_or_else(|| f.err("range exceeds the resource"))?;
+        let gpa = backing_gpa(&f, allocs, backing, offset, range.len(), false)?;
+        mem.read_physical(gpa, &mut res.data[range]);
+        Ok(())
+    }
+
+    fn upload_resource(&mut self, packet: &AerogpuCmdPacket<'_>) -> Result<(), String> {
+        let (cmd, bytes) = packet
+            .decode_upload_resource_payload_le()
+            .map_err(|err| format!("UPLOAD_RESOURCE: {err}"))?;
+        let f = Fields::new(packet, "UPLOAD_RESOURCE", 0)?;
+        let res = self.resource_mut(&f, cmd.resource_handle)?;
+        let range = checked_range(cmd.offset_bytes, cmd.size_bytes, res.data.len())
+            .ok_or_else(|| f.err("range exceeds the resource"))?;
+        res.data[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn copy_buffer(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let cmd = packet
+            .decode_copy_buffer_payload_le()
+            .map_err(|err| format!("COPY_BUFFER: {err}"))?;
+        let f = Fields::new(packet, "COPY_BUFFER", 0)?;
+        let (dst, src) = (cmd.dst_buffer, cmd.src_buffer);
+        let buffer_len = |this: &Self, handle: u32| match this.resources.get(&handle) {
+            Some(res) if res.texture.is_none() => Ok(res.data.len()),
+            Some(_) => Err(f.err(format_args!("handle {handle} is not a buffer"))),
+            None => Err(f.err(format_args!("unknown handle {handle}"))),
+        };
+        let src_range = checked_range(cmd.src_offset_bytes, cmd.size_bytes, buffer_len(self, src)?)
+            .ok_or_else(|| f.err("source range exceeds the buffer"))?;
+        let dst_range = checked_range(cmd.dst_offset_bytes, cmd.size_bytes, buffer_len(self, dst)?)
+            .ok_or_else(|| f.err("destination range exceeds the buffer"))?;
+
+        if dst == src {
+            let data = &mut self.resource_mut(&f, dst)?.data;
+            data.copy_within(src_range, dst_range.start);
+        } else {
+            let src_bytes = self.resources[&src].data[src_range].to_vec();
+            self.resource_mut(&f, dst)?.data[dst_range.clone()].copy_from_slice(&src_bytes);
+        }
+
+        if cmd.flags & AEROGPU_COPY_FLAG_WRITEBACK_DST != 0 {
+            let res = &self.resources[&dst];
+            writeback(&f, mem, allocs, res, dst_range)?;
+        }
+        Ok(())
+    }
+
+    fn copy_texture2d(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        allocs: &HashMap<u32, Alloc>,
+        packet: &AerogpuCmdPacket<'_>,
+    ) -> Result<(), String> {
+        let cmd = packet
+            .decode_copy_texture2d_payload_le()
+            .map_err(|err| format!("COPY_TEXTURE2D: {err}"))?;
+        let f = Fields::new(packet, "COPY_TEXTURE2D", 0)?;
+        if cmd.dst_mip_level != 0
+            || cmd.dst_array_layer != 0
+            || cmd.src_mip_level != 0
+            || cmd.src_array_layer != 0
+        {
+            return Err(f.err("only mip 0 / layer 0 is supported"));
+        }
+        let texture = |this: &Self, handle: u32| match this.resources.get(&handle) {
+            Some(Resource {
+                texture: Some(desc),
+                ..
+            }) => Ok(*desc),
+            Some(_) => Err(f.err(format_args!("handle {handle} is not a texture"))),
+            None => Err(f.err(format_args!("unknown handle {handle}"))),
+        };
+        let (dst, src) = (cmd.dst_texture, cmd.src_texture);
+        let (dst_desc, src_desc) = (texture(self, dst)?, texture(self, src)?);
+        if channel_order(dst_desc.format).map(|(order, _)| order)
+            != channel_order(src_desc.format).map(|(order, _)| order)
+        {
+            return Err(f.err(format_args!(
+                "incompatible formats {:?} -> {:?}",
+                src_desc.format, dst_desc.format
+            )));
+        }
+        let in_bounds = |desc: TextureDesc, x: u32, y: u32| {
+            x.checked_add(cmd.width)
+                .is_some_and(|end| end <= desc.width)
+                && y.checked_add(cmd.height)
+                    .is_some_and(|end| end <= desc.height)
+        };
+        if !in_bounds(src_desc, cmd.src_x, cmd.src_y) || !in_bounds(dst_desc, cmd.dst_x, cmd.dst_y)
+        {
+            return Err(f.err(format_args!(
+                "{}x{} rect exceeds the source or destination texture",
+                { cmd.width },
+                { cmd.height }
+            )));
+        }
+
+        let row_bytes = cmd.width as usize * BYTES_PER_PIXEL;
+        let row_offset = |desc: TextureDesc, x: u32, y: u32| {
+            y as usize * desc.row_pitch + x as usize * BYTES_PER_PIXEL
+        };
+        // Stage the source rect so overlapping same-texture copies behave like a real blit.
+        let mut staged = Vec::with_capacity(row_bytes * cmd.height as usize);
+        let src_data = &self.resources[&src].data;
+        for row in 0..cmd.height {
+            let start = row_offset(src_desc, cmd.src_x, cmd.src_y + row);
+            staged.extend_from_slice(&src_data[start..start + row_bytes]);
+        }
+        let dst_data = &mut self.resource_mut(&f, dst)?.data;
+        for (row, bytes) in (0..cmd.height).zip(staged.chunks_exact(row_bytes.max(1))) {
+            let start = row_offset(dst_desc, cmd.dst_x, cmd.dst_y + row);
+            dst_data[start..start + row_bytes].copy_from_slice(bytes);
+        }
+
+        if cmd.flags & AEROGPU_COPY_FLAG_WRITEBACK_DST != 0 && cmd.height != 0 {
+            let first = row_offset(dst_desc, cmd.dst_x, cmd.dst_y);
+            let last = row_offset(dst_desc, cmd.dst_x, cmd.dst_y + cmd.height - 1) + row_bytes;
+            writeback(&f, mem, allocs, &self.resources[&dst], first..last)?;
+        }
+        Ok(())
+    }
+
+    fn set_render_targets(&mut self, packet: &AerogpuCmdPacket<'_>) -> Result<(), String> {
+        let f = Fields::new(
+            packet,
+            "SET_RENDER_TARGETS",
+            8 + 4 * AEROGPU_MAX_RENDER_TARGETS,
+        )?;
+        let count = f.u32(0) as usize;
+        if count > AEROGPU_MAX_RENDER_TARGETS {
+            return Err(f.err(format_args!("color_count {count} exceeds the maximum")));
+        }
+        let color0 = if count == 0 { 0 } else { f.u32(8) };
+        if color0 != 0 && !matches!(self.resources.get(&color0), Some(res) if res.texture.is_some())
+        {
+            return Err(f.err(format_args!("color target {color0} is not a texture")));
+        }
+        self.render_target = color0;
+        Ok(())
+    }
+
+    fn clear(&mut self, packet: &AerogpuCmdPacket<'_>) -> Result<(), String> {
+        let f = Fields::new(packet, "CLEAR", 20)?;
+        if f.u32(0) & AEROGPU_CLEAR_COLOR == 0 {
+            // Depth/stencil targets are not modelled.
+            return Ok(());
+        }
+        let rt = self.render_target;
+        if rt == 0 {
+            return Err(f.err("no render target bound"));
+        }
+        let unorm = |offset| {
+            let value = f32::from_bits(f.u32(offset));
+            // `NaN` clamps to 0.
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        let [r, g, b, a] = [unorm(4), unorm(8), unorm(12), unorm(16)];
+
+        let res = self.resource_mut(&f, rt)?;
+        let desc = res.texture.expect("render targets are textures");
+        let (order, opaque) = channel_order(desc.format).expect("validated at creation");
+        let a = if opaque { 0xFF } else { a };
+        let px = match order {
+            ChannelOrder::Bgra => [b, g, r, a],
+            ChannelOrder::Rgba => [r, g, b, a],
+        };
+        let row_bytes = desc.width as usize * BYTES_PER_PIXEL;
+        for row in res.data.chunks_exact_mut(desc.row_pitch) {
+            for dst in row[..row_bytes].chunks_exact_mut(BYTES_PER_PIXEL) {
+                dst.copy_from_slice(&px);
+            }
+        }
+        Ok(())
+    }
+
+    fn present(&mut self, packet: &AerogpuCmdPacket<'_>) -> Result<(), String> {
+        let f = Fields::new(packet, "PRESENT", 8)?;
+        let scanout_id = f.u32(0);
+        let rt = self.render_target;
+        if rt == 0 {
+            return Err(f.err("no render target bound"));
+        }
+        let res = &self.resources[&rt];
+        let desc = res.texture.expect("render targets are textures");
+        self.presented
+            .insert(scanout_id, Self::texture_to_rgba8(desc, &res.data));
+        Ok(())
+    }
+}
+
+fn backing_gpa(
+    f: &Fields<'_>,
+    allocs: &HashMap<u32, Alloc>,
+    backing: Backing,
+    offset: u64,
+    len: usize,
+    write: bool,
+) -> Result<u64, String> {
+    let alloc = allocs.get(&backing.alloc_id).ok_or_else(|| {
+        f.err(format_args!(
+            "alloc_id {} is missing from the submission's alloc table",
+            backing.alloc_id
+        ))
+    })?;
+    if write && alloc.read_only {
+        return Err(f.err(format_args!("alloc_id {} is read-only", backing.alloc_id)));
+    }
+    let start = backing.offset.checked_add(offset);
+    if start
+        .and_then(|start| start.checked_add(len as u64))
+        .is_none_or(|end| end > alloc.size)
+    {
+        return Err(f.err(format_args!(
+            "backing range exceeds alloc_id {} ({} bytes)",
+            backing.alloc_id, alloc.size
+        )));
+    }
+    Ok(alloc.gpa + start.unwrap_or(0))
+}
+
+fn writeback(
+    f: &Fields<'_>,
+    mem: &mut dyn MemoryBus,
+    allocs: &HashMap<u32, Alloc>,
+    res: &Resource,
+    range: std::ops::Range<usize>,
+) -> Result<(), String> {
+    let backing = res
+        .backing
+        .ok_or_else(|| f.err("WRITEBACK_DST requires a guest-backed destination"))?;
+    let gpa = backing_gpa(f, allocs, backing, range.start as u64, range.len(), true)?;
+    mem.write_physical(gpa, &res.data[range]);
+    Ok(())
+}
+
+fn decode_alloc_table(bytes: &[u8]) -> Result<HashMap<u32, Alloc>, String> {
+    let header = AerogpuAllocTableHeader::decode_from_le_bytes(bytes)
+        .map_err(|err| format!("failed to decode alloc table header: {err:?}"))?;
+    header
+        .validate_prefix()
+        .map_err(|err| format!("invalid alloc table header: {err:?}"))?;
+
+    let table_size = header.size_bytes as usize;
+    if table_size > bytes.len() {
+        return Err(format!(
+            "alloc table header size_bytes={} exceeds buffer len={}",
+            header.size_bytes,
+            bytes.len()
+        ));
+    }
+    let stride = header.entry_stride_bytes as usize;
+    if stride < AerogpuAllocEntry::SIZE_BYTES {
+        return Err(format!(
+            "alloc table entry_stride_bytes={} is smaller than expected {}",
+            header.entry_stride_bytes,
+            AerogpuAllocEntry::SIZE_BYTES
+        ));
+    }
+
+    let mut out = HashMap::new();
+    for idx in 0..header.entry_count as usize {
+        let start = idx
+            .checked_mul(stride)
+            .and_then(|offset| offset.checked_add(AerogpuAllocTableHeader::SIZE_BYTES))
+            .filter(|start| {
+                start
+                    .checked_add(AerogpuAllocEntry::SIZE_BYTES)
+                    .is_some_and(|end| end <= table_size)
+            })
+            .ok_or_else(|| format!("alloc table entry {idx} is out of bounds"))?;
+        let entry = AerogpuAllocEntry::decode_from_le_bytes(&bytes[start..])
+            .map_err(|err| format!("failed to decode alloc table entry {idx}: {err:?}"))?;
+        if entry.alloc_id == 0 {
+            return Err(format!("alloc table entry {idx} has alloc_id=0"));
+        }
+        if entry.size_bytes == 0 {
+            return Err(format!(
+                "alloc table entry {idx} has size_bytes=0 (alloc_id={})",
+                entry.alloc_id
+            ));
+        }
+        if entry.gpa.checked_add(entry.size_bytes).is_none() {
+            return Err(format!(
+                "alloc table entry {idx} gpa+size overflows (gpa=0x{:x}, size=0x{:x})",
+                entry.gpa, entry.size_bytes
+            ));
+        }
+        let alloc = Alloc {
+            gpa: entry.gpa,
+            size: entry.size_bytes,
+            read_only: entry.flags & AEROGPU_ALLOC_FLAG_READONLY != 0,
+        };
+        if out.insert(entry.alloc_id, alloc).is_some() {
+            return Err(format!(
+                "alloc table contains duplicate alloc_id={}",
+                entry.alloc_id
+            ));
+        }
+    }
+    Ok(out)
+}
+
+impl AeroGpuCommandBackend for SoftwareAeroGpuBackend {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn submit(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        submission: AeroGpuBackendSubmission,
+    ) -> Result<(), String> {
+        let result = self.execute(mem, &submission);
+        // Fences always make progress; failures are reported through the completion.
+        self.completed.push_back(AeroGpuBackendCompletion {
+            fence: submission.signal_fence,
+            error: result.err(),
+        });
+        Ok(())
+    }
+
+    fn poll_completions(&mut self) -> Vec<AeroGpuBackendCompletion> {
+        self.completed.drain(..).collect()
+    }
+
+    fn read_scanout_rgba8(&mut self, scanout_id: u32) -> Option<AeroGpuBackendScanout> {
+        self.presented.get(&scanout_id).cloned()
+    }
+}
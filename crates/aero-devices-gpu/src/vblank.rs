@@ -17,6 +17,18 @@ pub fn period_ns_from_hz(vblank_hz: Option<u32>) -> Option<u64> {
     })
 }
 
+/// Convert a vblank refresh rate in millihertz (e.g. `59_940` for 59.94Hz) into a vblank period in
+/// nanoseconds.
+///
+/// Returns `None` for a rate of 0. Uses the same ceil division as [`period_ns_from_hz`], so
+/// `60_000` mHz yields the same period as 60Hz.
+pub fn period_ns_from_millihertz(millihertz: u32) -> Option<u64> {
+    if millihertz == 0 {
+        return None;
+    }
+    Some(1_000_000_000_000u64.div_ceil(u64::from(millihertz)))
+}
+
 /// Clamp a vblank period (nanoseconds) to the guest-visible `u32` register representation.
 pub fn period_ns_to_reg(period_ns: u64) -> u32 {
     period_ns.min(u64::from(u32::MAX)) as u32
@@ -34,6 +46,17 @@ mod tests {
         assert_eq!(period_ns_from_hz(Some(60)), Some(16_666_667));
     }
 
+    #[test]
+    fn period_ns_from_millihertz_matches_hz() {
+        assert_eq!(period_ns_from_millihertz(0), None);
+        assert_eq!(
+            period_ns_from_millihertz(60_000),
+            period_ns_from_hz(Some(60))
+        );
+        assert_eq!(period_ns_from_millihertz(30_000), Some(33_333_334));
+        assert_eq!(period_ns_from_millihertz(59_940), Some(16_683_351));
+    }
+
     #[test]
     fn period_ns_to_reg_clamps_to_u32() {
         assert_eq!(period_ns_to_reg(0), 0);
@@ -0,0 +1,439 @@
+use aero_devices_gpu::{
+    AeroGpuBackendScanout, AeroGpuBackendSubmission, AeroGpuCommandBackend, SoftwareAeroGpuBackend,
+};
+use aero_protocol::aerogpu::aerogpu_cmd::{
+    AEROGPU_CLEAR_COLOR, AEROGPU_COPY_FLAG_WRITEBACK_DST, AEROGPU_RESOURCE_USAGE_RENDER_TARGET,
+    AEROGPU_RESOURCE_USAGE_SCANOUT, AEROGPU_RESOURCE_USAGE_TEXTURE,
+};
+use aero_protocol::aerogpu::aerogpu_pci::{AerogpuFormat, AEROGPU_ABI_VERSION_U32};
+use aero_protocol::aerogpu::aerogpu_ring::{
+    AerogpuAllocEntry, AerogpuAllocTableHeader, AEROGPU_ALLOC_FLAG_READONLY,
+    AEROGPU_ALLOC_TABLE_MAGIC,
+};
+use aero_protocol::aerogpu::cmd_writer::AerogpuCmdWriter;
+use memory::{Bus, MemoryBus};
+use pretty_assertions::assert_eq;
+
+const RT: u32 = 1;
+const TEX: u32 = 2;
+const BUF_A: u32 = 3;
+const BUF_B: u32 = 4;
+
+const RT_USAGE: u32 = AEROGPU_RESOURCE_USAGE_RENDER_TARGET | AEROGPU_RESOURCE_USAGE_SCANOUT;
+
+/// `(alloc_id, flags, gpa, size_bytes)`.
+fn alloc_table(entries: &[(u32, u32, u64, u64)]) -> Vec<u8> {
+    let size = AerogpuAllocTableHeader::SIZE_BYTES + entries.len() * AerogpuAllocEntry::SIZE_BYTES;
+    let mut out = Vec::with_capacity(size);
+    for word in [
+        AEROGPU_ALLOC_TABLE_MAGIC,
+        AEROGPU_ABI_VERSION_U32,
+        size as u32,
+        entries.len() as u32,
+        AerogpuAllocEntry::SIZE_BYTES as u32,
+        0,
+    ] {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for &(alloc_id, flags, gpa, size_bytes) in entries {
+        out.extend_from_slice(&alloc_id.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&gpa.to_le_bytes());
+        out.extend_from_slice(&size_bytes.to_le_bytes());
+        out.extend_from_slice(&0u64.to_le_bytes());
+    }
+    out
+}
+
+/// Submit `cmd_stream` and return the completion error (if any).
+fn submit(
+    backend: &mut SoftwareAeroGpuBackend,
+    mem: &mut dyn MemoryBus,
+    fence: u64,
+    cmd_stream: Vec<u8>,
+    alloc_table: Option<Vec<u8>>,
+) -> Option<String> {
+    backend
+        .submit(
+            mem,
+            AeroGpuBackendSubmission {
+                flags: 0,
+                context_id: 0,
+                engine_id: 0,
+                signal_fence: fence,
+                cmd_stream,
+                alloc_table,
+            },
+        )
+        .expect("software backend never rejects a submission");
+    let completions = backend.poll_completions();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].fence, fence);
+    completions[0].error.clone()
+}
+
+fn run(backend: &mut SoftwareAeroGpuBackend, build: impl FnOnce(&mut AerogpuCmdWriter)) {
+    let mut w = AerogpuCmdWriter::new();
+    build(&mut w);
+    let mut mem = Bus::new(0x1000);
+    assert_eq!(submit(backend, &mut mem, 1, w.finish(), None), None);
+}
+
+fn run_err(
+    backend: &mut SoftwareAeroGpuBackend,
+    build: impl FnOnce(&mut AerogpuCmdWriter),
+) -> String {
+    let mut w = AerogpuCmdWriter::new();
+    build(&mut w);
+    let mut mem = Bus::new(0x1000);
+    submit(backend, &mut mem, 1, w.finish(), None).expect("submission should fail")
+}
+
+/// A command stream builder paired with the error substring it should produce.
+type ErrorCase = (&'static str, Box<dyn Fn(&mut AerogpuCmdWriter)>);
+
+fn create_rt(w: &mut AerogpuCmdWriter, format: AerogpuFormat, width: u32, height: u32) {
+    w.create_texture2d(RT, RT_USAGE, format as u32, width, height, 1, 1, 0, 0, 0);
+}
+
+fn solid(width: u32, height: u32, px: [u8; 4]) -> AeroGpuBackendScanout {
+    AeroGpuBackendScanout {
+        width,
+        height,
+        rgba8: px.repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn clear_and_present_converts_to_rgba8() {
+    for (format, expected) in [
+        (AerogpuFormat::B8G8R8A8Unorm, [0xFF, 0x80, 0x00, 0x40]),
+        (AerogpuFormat::B8G8R8X8Unorm, [0xFF, 0x80, 0x00, 0xFF]),
+        (AerogpuFormat::R8G8B8A8Unorm, [0xFF, 0x80, 0x00, 0x40]),
+        (AerogpuFormat::R8G8B8X8UnormSrgb, [0xFF, 0x80, 0x00, 0xFF]),
+    ] {
+        let mut backend = SoftwareAeroGpuBackend::new();
+        run(&mut backend, |w| {
+            create_rt(w, format, 3, 2);
+            w.set_render_targets(&[RT], 0);
+            // Out-of-range and NaN channels clamp; 0.5 rounds to 0x80.
+            w.clear(AEROGPU_CLEAR_COLOR, [2.0, 0.5, f32::NAN, 0.25], 1.0, 0);
+            w.present(0, 0);
+        });
+        assert_eq!(
+            backend.read_scanout_rgba8(0),
+            Some(solid(3, 2, expected)),
+            "{format:?}"
+        );
+        assert_eq!(backend.read_scanout_rgba8(1), None);
+    }
+}
+
+#[test]
+fn upload_and_copy_texture_rect() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    // 2x2 BGRA source with distinct pixels, padded to a 16-byte row pitch.
+    let mut texels = Vec::new();
+    for row in [[1u8, 2, 3, 4, 5, 6, 7, 8], [9, 10, 11, 12, 13, 14, 15, 16]] {
+        texels.extend_from_slice(&row);
+        texels.extend_from_slice(&[0xEE; 8]);
+    }
+    run(&mut backend, |w| {
+        create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 4, 3);
+        w.create_texture2d(
+            TEX,
+            AEROGPU_RESOURCE_USAGE_TEXTURE,
+            AerogpuFormat::B8G8R8X8Unorm as u32,
+            2,
+            2,
+            1,
+            1,
+            16,
+            0,
+            0,
+        );
+        w.upload_resource(TEX, 0, &texels);
+        w.set_render_targets(&[RT], 0);
+        w.clear(AEROGPU_CLEAR_COLOR, [0.0, 0.0, 0.0, 1.0], 1.0, 0);
+        w.copy_texture2d(RT, TEX, 0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 0);
+        w.present(0, 0);
+    });
+
+    let black = [0, 0, 0, 0xFF];
+    let mut expected = solid(4, 3, black);
+    for (x, y, px) in [
+        (1, 1, [3, 2, 1, 4]),
+        (2, 1, [7, 6, 5, 8]),
+        (1, 2, [11, 10, 9, 12]),
+        (2, 2, [15, 14, 13, 16]),
+    ] {
+        let offset = (y * 4 + x) * 4;
+        expected.rgba8[offset..offset + 4].copy_from_slice(&px);
+    }
+    assert_eq!(backend.read_scanout_rgba8(0), Some(expected));
+    // Readback of the X8 source forces alpha to opaque.
+    assert_eq!(
+        backend.read_texture_rgba8(TEX).unwrap().rgba8[..4],
+        [3, 2, 1, 0xFF]
+    );
+}
+
+#[test]
+fn overlapping_self_copy_behaves_like_a_blit() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    let row: Vec<u8> = (0u8..4).flat_map(|i| [i, i, i, 0xFF]).collect();
+    run(&mut backend, |w| {
+        create_rt(w, AerogpuFormat::R8G8B8A8Unorm, 4, 1);
+        w.upload_resource(RT, 0, &row);
+        w.copy_texture2d(RT, RT, 0, 0, 0, 0, 1, 0, 0, 0, 3, 1, 0);
+    });
+    let expected: Vec<u8> = [0u8, 0, 1, 2]
+        .iter()
+        .flat_map(|&i| [i, i, i, 0xFF])
+        .collect();
+    assert_eq!(backend.read_texture_rgba8(RT).unwrap().rgba8, expected);
+}
+
+#[test]
+fn guest_backed_resources_load_and_write_back() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    let mut mem = Bus::new(0x4000);
+    mem.write_physical(0x1000, &[0xAB; 16]);
+    let allocs = alloc_table(&[(10, 0, 0x1000, 0x100), (11, 0, 0x2000, 0x100)]);
+
+    let mut w = AerogpuCmdWriter::new();
+    w.create_buffer(BUF_A, 0, 16, 10, 0);
+    w.create_buffer(BUF_B, 0, 16, 11, 8);
+    w.copy_buffer(BUF_B, BUF_A, 0, 4, 8, AEROGPU_COPY_FLAG_WRITEBACK_DST);
+    assert_eq!(
+        submit(&mut backend, &mut mem, 1, w.finish(), Some(allocs.clone())),
+        None
+    );
+
+    let mut out = [0u8; 24];
+    mem.read_physical(0x2000, &mut out);
+    assert_eq!(out[..8], [0; 8]);
+    assert_eq!(out[8..16], [0xAB; 8]);
+    assert_eq!(out[16..], [0; 8]);
+
+    // Guest updates its copy; the host copy only changes after RESOURCE_DIRTY_RANGE.
+    mem.write_physical(0x1000, &[0xCD; 16]);
+    let mut w = AerogpuCmdWriter::new();
+    w.copy_buffer(BUF_B, BUF_A, 0, 0, 4, AEROGPU_COPY_FLAG_WRITEBACK_DST);
+    w.resource_dirty_range(BUF_A, 0, 16);
+    w.copy_buffer(BUF_B, BUF_A, 4, 0, 4, AEROGPU_COPY_FLAG_WRITEBACK_DST);
+    assert_eq!(
+        submit(&mut backend, &mut mem, 2, w.finish(), Some(allocs)),
+        None
+    );
+    mem.read_physical(0x2000, &mut out);
+    assert_eq!(out[8..16], [0xAB, 0xAB, 0xAB, 0xAB, 0xCD, 0xCD, 0xCD, 0xCD]);
+}
+
+#[test]
+fn writeback_into_readonly_alloc_is_rejected() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    let mut mem = Bus::new(0x4000);
+    let allocs = alloc_table(&[(10, AEROGPU_ALLOC_FLAG_READONLY, 0x1000, 0x100)]);
+    let mut w = AerogpuCmdWriter::new();
+    w.create_buffer(BUF_A, 0, 16, 10, 0);
+    w.copy_buffer(BUF_A, BUF_A, 0, 8, 4, AEROGPU_COPY_FLAG_WRITEBACK_DST);
+    let err = submit(&mut backend, &mut mem, 1, w.finish(), Some(allocs)).unwrap();
+    assert!(err.contains("read-only"), "{err}");
+}
+
+#[test]
+fn invalid_commands_report_errors() {
+    let cases: Vec<ErrorCase> = vec![
+        ("unknown handle", Box::new(|w| w.destroy_resource(7))),
+        (
+            "still in use",
+            Box::new(|w| {
+                create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 1, 1);
+                create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 1, 1);
+            }),
+        ),
+        (
+            "unsupported format",
+            Box::new(|w| create_rt(w, AerogpuFormat::D24UnormS8Uint, 1, 1)),
+        ),
+        (
+            "invalid dimensions",
+            Box::new(|w| create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 0, 1)),
+        ),
+        (
+            "missing from the submission's alloc table",
+            Box::new(|w| w.create_buffer(BUF_A, 0, 16, 99, 0)),
+        ),
+        (
+            "range exceeds the resource",
+            Box::new(|w| {
+                w.create_buffer(BUF_A, 0, 4, 0, 0);
+                w.upload_resource(BUF_A, 2, &[0; 4]);
+            }),
+        ),
+        (
+            "exceeds the source or destination texture",
+            Box::new(|w| {
+                create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 2, 2);
+                w.copy_texture2d(RT, RT, 0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 0);
+            }),
+        ),
+        (
+            "incompatible formats",
+            Box::new(|w| {
+                create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 2, 2);
+                w.create_texture2d(
+                    TEX,
+                    0,
+                    AerogpuFormat::R8G8B8A8Unorm as u32,
+                    2,
+                    2,
+                    1,
+                    1,
+                    0,
+                    0,
+                    0,
+                );
+                w.copy_texture2d(RT, TEX, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0);
+            }),
+        ),
+        ("no render target bound", Box::new(|w| w.present(0, 0))),
+        (
+            "not supported by the software backend",
+            Box::new(|w| w.draw(3, 1, 0, 0)),
+        ),
+    ];
+    for (expected, build) in cases {
+        let mut backend = SoftwareAeroGpuBackend::new();
+        let err = run_err(&mut backend, |w| build(w));
+        assert!(err.contains(expected), "expected {expected:?} in {err:?}");
+    }
+}
+
+#[test]
+fn errors_stop_the_submission_but_fences_still_complete() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    let err = run_err(&mut backend, |w| {
+        create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 1, 1);
+        w.set_render_targets(&[RT], 0);
+        w.destroy_resource(TEX);
+        w.present(0, 0);
+    });
+    assert!(err.contains("DESTROY_RESOURCE"), "{err}");
+    assert_eq!(backend.read_scanout_rgba8(0), None);
+
+    // Destroying the bound render target unbinds it.
+    let err = run_err(&mut backend, |w| {
+        w.destroy_resource(RT);
+        w.present(0, 0);
+    });
+    assert!(err.contains("no render target bound"), "{err}");
+
+    let mut mem = Bus::new(0x1000);
+    assert_eq!(submit(&mut backend, &mut mem, 5, Vec::new(), None), None);
+}
+
+#[test]
+fn reset_drops_resources_and_scanouts() {
+    let mut backend = SoftwareAeroGpuBackend::new();
+    run(&mut backend, |w| {
+        create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 1, 1);
+        w.set_render_targets(&[RT], 0);
+        w.present(0, 0);
+    });
+    assert!(backend.read_scanout_rgba8(0).is_some());
+    backend.reset();
+    assert_eq!(backend.read_scanout_rgba8(0), None);
+    assert_eq!(backend.read_texture_rgba8(RT), None);
+    // The handle is free again after reset.
+    run(&mut backend, |w| {
+        create_rt(w, AerogpuFormat::B8G8R8A8Unorm, 1, 1)
+    });
+}
+
+/// Tiny deterministic xorshift so the mutation corpus is reproducible without extra deps.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn mutated_streams_never_panic() {
+    let mut w = AerogpuCmdWriter::new();
+    create_rt(&mut w, AerogpuFormat::B8G8R8A8Unorm, 8, 8);
+    w.create_texture2d(
+        TEX,
+        AEROGPU_RESOURCE_USAGE_TEXTURE,
+        AerogpuFormat::B8G8R8A8Unorm as u32,
+        4,
+        4,
+        1,
+        1,
+        0,
+        10,
+        0,
+    );
+    w.create_buffer(BUF_A, 0, 64, 10, 0);
+    w.upload_resource(BUF_A, 0, &[0x11; 32]);
+    w.resource_dirty_range(TEX, 0, 64);
+    w.copy_buffer(BUF_A, BUF_A, 32, 0, 16, AEROGPU_COPY_FLAG_WRITEBACK_DST);
+    w.set_render_targets(&[RT], 0);
+    w.clear(AEROGPU_CLEAR_COLOR, [0.1, 0.2, 0.3, 0.4], 1.0, 0);
+    w.copy_texture2d(RT, TEX, 0, 0, 0, 0, 2, 2, 0, 0, 4, 4, 0);
+    w.present(0, 0);
+    let seed_stream = w.finish();
+    let allocs = alloc_table(&[(10, 0, 0x1000, 0x100)]);
+
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut mem = Bus::new(0x2000);
+    let mut backend = SoftwareAeroGpuBackend::new();
+    assert_eq!(
+        submit(
+            &mut backend,
+            &mut mem,
+            1,
+            seed_stream.clone(),
+            Some(allocs.clone())
+        ),
+        None
+    );
+
+    for fence in 2..2000u64 {
+        let mut stream = seed_stream.clone();
+        let mut table = allocs.clone();
+        for _ in 0..1 + rng.next() % 4 {
+            let target = if rng.next().is_multiple_of(4) {
+                &mut table
+            } else {
+                &mut stream
+            };
+            let idx = (rng.next() as usize) % target.len();
+            target[idx] = rng.next() as u8;
+        }
+        if rng.next().is_multiple_of(8) {
+            let len = (rng.next() as usize) % stream.len();
+            stream.truncate(len);
+        }
+        let _ = submit(&mut backend, &mut mem, fence, stream, Some(table));
+        if rng.next().is_multiple_of(16) {
+            backend.reset();
+        }
+    }
+
+    // Pure garbage, including streams that start with a valid header.
+    for fence in 0..500u64 {
+        let len = (rng.next() as usize) % 256;
+        let mut stream: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+        if fence % 2 == 0 && stream.len() >= 24 {
+            stream[..24].copy_from_slice(&seed_stream[..24]);
+        }
+        let _ = submit(&mut backend, &mut mem, fence, stream, None);
+    }
+}
@@ -48,6 +48,9 @@ enum OutputSource {
 struct OutputByte {
     value: u8,
     source: OutputSource,
+    // Number of the device output byte this was produced from (see
+    // `Ps2Keyboard::output_bytes_queued`), or 0 if it did not come from a device queue.
+    number: u64,
 }
 
 // i8042 status register bits.
@@ -242,6 +245,8 @@ pub struct I8042Controller {
     output_buffer: Option<OutputByte>,
     pending_output: VecDeque<OutputByte>,
     dropped_output_bytes: u64,
+    keyboard_output_consumed: u64,
+    mouse_output_consumed: u64,
     pending_write: Option<PendingWrite>,
     last_write_was_command: bool,
 
@@ -269,6 +274,8 @@ impl I8042Controller {
             output_buffer: None,
             pending_output: VecDeque::new(),
             dropped_output_bytes: 0,
+            keyboard_output_consumed: 0,
+            mouse_output_consumed: 0,
             pending_write: None,
             last_write_was_command: false,
             keyboard: Ps2Keyboard::new(),
@@ -431,6 +438,15 @@ impl I8042Controller {
         self.service_output();
     }
 
+    /// Inject horizontal wheel movement (positive = right); see [`Ps2Mouse::inject_hwheel`].
+    pub fn inject_mouse_hwheel(&mut self, delta: i32) {
+        if !self.mouse_port_enabled() {
+            return;
+        }
+        self.mouse.inject_hwheel(delta);
+        self.service_output();
+    }
+
     pub fn inject_mouse_button(&mut self, button: Ps2MouseButton, pressed: bool) {
         if !self.mouse_port_enabled() {
             // When the AUX port is disabled, the controller suppresses mouse output. We still keep
@@ -457,6 +473,21 @@ impl I8042Controller {
         self.dropped_output_bytes
     }
 
+    /// Number (see [`Ps2Keyboard::output_bytes_queued`]) of the latest keyboard output byte the
+    /// guest has read from port 0x60, or 0 if none has been read since reset or snapshot restore.
+    ///
+    /// Bytes absorbed by Set-2 -> Set-1 translation (e.g. `F0` break prefixes) count as read
+    /// together with the translated byte that follows them.
+    pub fn keyboard_output_consumed(&self) -> u64 {
+        self.keyboard_output_consumed
+    }
+
+    /// Number (see [`Ps2Mouse::output_bytes_queued`]) of the latest mouse output byte the guest
+    /// has read from port 0x60, or 0 if none has been read since reset or snapshot restore.
+    pub fn mouse_output_consumed(&self) -> u64 {
+        self.mouse_output_consumed
+    }
+
     fn read_status(&mut self) -> u8 {
         let mut status = self.status;
         if self.last_write_was_command {
@@ -475,6 +506,16 @@ impl I8042Controller {
         self.status &= !STATUS_OBF;
         self.status &= !STATUS_AUX_OBF;
 
+        match out.source {
+            OutputSource::Keyboard => {
+                self.keyboard_output_consumed = self.keyboard_output_consumed.max(out.number);
+            }
+            OutputSource::Mouse => {
+                self.mouse_output_consumed = self.mouse_output_consumed.max(out.number);
+            }
+            OutputSource::Controller => {}
+        }
+
         // Immediately load any queued bytes and potentially raise the next IRQ.
         self.service_output();
         out.value
@@ -595,6 +636,7 @@ impl I8042Controller {
                     self.push_pending_output(OutputByte {
                         value,
                         source: OutputSource::Keyboard,
+                        number: 0,
                     });
                 }
                 PendingWrite::WriteToOutputBufferMouse => {
@@ -602,6 +644,7 @@ impl I8042Controller {
                     self.push_pending_output(OutputByte {
                         value,
                         source: OutputSource::Mouse,
+                        number: 0,
                     });
                 }
             }
@@ -674,6 +717,7 @@ impl I8042Controller {
         self.push_pending_output(OutputByte {
             value,
             source: OutputSource::Controller,
+            number: 0,
         });
         self.service_output();
     }
@@ -719,7 +763,7 @@ impl I8042Controller {
         if !self.keyboard_port_enabled() {
             return false;
         }
-        let Some(byte) = self.keyboard.pop_output() else {
+        let Some((byte, number)) = self.keyboard.pop_output_numbered() else {
             return false;
         };
 
@@ -728,12 +772,14 @@ impl I8042Controller {
                 self.push_pending_output(OutputByte {
                     value: out,
                     source: OutputSource::Keyboard,
+                    number,
                 });
             }
         } else {
             self.push_pending_output(OutputByte {
                 value: byte,
                 source: OutputSource::Keyboard,
+                number,
             });
         }
         true
@@ -743,12 +789,13 @@ impl I8042Controller {
         if !self.mouse_port_enabled() {
             return false;
         }
-        let Some(byte) = self.mouse.pop_output() else {
+        let Some((byte, number)) = self.mouse.pop_output_numbered() else {
             return false;
         };
         self.push_pending_output(OutputByte {
             value: byte,
             source: OutputSource::Mouse,
+            number,
         });
         true
     }
@@ -933,6 +980,8 @@ impl IoSnapshot for I8042Controller {
         self.output_port = OUTPUT_PORT_RESET;
         self.pending_output.clear();
         self.dropped_output_bytes = 0;
+        self.keyboard_output_consumed = 0;
+        self.mouse_output_consumed = 0;
         self.pending_write = None;
         self.last_write_was_command = false;
         self.translator = Set2ToSet1::default();
@@ -960,7 +1009,11 @@ impl IoSnapshot for I8042Controller {
                 _ => OutputSource::Controller,
             };
             d.finish()?;
-            Some(OutputByte { value, source })
+            Some(OutputByte {
+                value,
+                source,
+                number: 0,
+            })
         } else {
             None
         };
@@ -986,7 +1039,11 @@ impl IoSnapshot for I8042Controller {
                     2 => OutputSource::Mouse,
                     _ => OutputSource::Controller,
                 };
-                self.push_pending_output(OutputByte { value, source });
+                self.push_pending_output(OutputByte {
+                    value,
+                    source,
+                    number: 0,
+                });
             }
             d.finish()?;
         }
@@ -25,6 +25,8 @@ pub struct Ps2Keyboard {
     scanning_enabled: bool,
     expecting_data: Option<ExpectingData>,
     out: VecDeque<u8>,
+    // Bytes ever appended to `out`, including ones later dropped on overflow.
+    queued_bytes: u64,
 }
 
 impl Ps2Keyboard {
@@ -36,6 +38,7 @@ impl Ps2Keyboard {
             scanning_enabled: true,
             expecting_data: None,
             out: VecDeque::new(),
+            queued_bytes: 0,
         }
     }
 
@@ -62,11 +65,26 @@ impl Ps2Keyboard {
         self.out.pop_front()
     }
 
+    /// Total number of bytes queued for the controller so far, including bytes later dropped on
+    /// overflow. Output bytes are numbered from 1 in queueing order, so this is also the number of
+    /// the most recently queued byte.
+    pub fn output_bytes_queued(&self) -> u64 {
+        self.queued_bytes
+    }
+
+    /// Like [`Self::pop_output`], also returning the byte's number (see
+    /// [`Self::output_bytes_queued`]).
+    pub(crate) fn pop_output_numbered(&mut self) -> Option<(u8, u64)> {
+        let byte = self.out.pop_front()?;
+        Some((byte, self.queued_bytes - self.out.len() as u64))
+    }
+
     fn push_out(&mut self, byte: u8) {
         if self.out.len() >= MAX_OUTPUT_BYTES {
             let _ = self.out.pop_front();
         }
         self.out.push_back(byte);
+        self.queued_bytes += 1;
     }
 
     pub fn inject_key(&mut self, scancode: Set2Scancode, pressed: bool) {
@@ -123,6 +141,7 @@ impl Ps2Keyboard {
         // malicious caller passing a multi-megabyte slice). Iterating over the entire slice would
         // be wasted work because the output queue is bounded: we can only retain the last
         // `MAX_OUTPUT_BYTES` bytes anyway.
+        self.queued_bytes += bytes.len() as u64;
         if bytes.len() >= MAX_OUTPUT_BYTES {
             self.out.clear();
             let start = bytes.len() - MAX_OUTPUT_BYTES;
@@ -61,6 +61,10 @@ pub struct Ps2Mouse {
     sample_rate: u8,
     reporting_enabled: bool,
     device_id: u8,
+    // IntelliMouse Explorer 4.0 horizontal scrolling, enabled by a second knock (200, 80, 40) in
+    // 5-button mode. Windows never sends the knock, so its drivers only ever see the standard
+    // Explorer 4th byte.
+    hwheel_enabled: bool,
 
     buttons: u8,
     dx: i32,
@@ -70,6 +74,8 @@ pub struct Ps2Mouse {
     sample_rate_seq: [u8; 3],
     expecting_data: Option<ExpectingData>,
     out: VecDeque<u8>,
+    // Bytes ever appended to `out`, including ones later dropped on overflow.
+    queued_bytes: u64,
 }
 
 impl Ps2Mouse {
@@ -81,6 +87,7 @@ impl Ps2Mouse {
             sample_rate: 100,
             reporting_enabled: false,
             device_id: 0x00,
+            hwheel_enabled: false,
             buttons: 0,
             dx: 0,
             dy: 0,
@@ -88,6 +95,7 @@ impl Ps2Mouse {
             sample_rate_seq: [0; 3],
             expecting_data: None,
             out: VecDeque::new(),
+            queued_bytes: 0,
         }
     }
 
@@ -99,11 +107,31 @@ impl Ps2Mouse {
         self.out.pop_front()
     }
 
+    /// Total number of bytes queued for the controller so far, including bytes later dropped on
+    /// overflow. Output bytes are numbered from 1 in queueing order, so this is also the number of
+    /// the most recently queued byte.
+    pub fn output_bytes_queued(&self) -> u64 {
+        self.queued_bytes
+    }
+
+    /// Like [`Self::pop_output`], also returning the byte's number (see
+    /// [`Self::output_bytes_queued`]).
+    pub(crate) fn pop_output_numbered(&mut self) -> Option<(u8, u64)> {
+        let byte = self.out.pop_front()?;
+        Some((byte, self.queued_bytes - self.out.len() as u64))
+    }
+
+    /// Number of bytes that can still be queued before the output buffer starts dropping data.
+    pub fn output_free(&self) -> usize {
+        MAX_OUTPUT_BYTES.saturating_sub(self.out.len())
+    }
+
     fn push_out(&mut self, byte: u8) {
         if self.out.len() >= MAX_OUTPUT_BYTES {
             let _ = self.out.pop_front();
         }
         self.out.push_back(byte);
+        self.queued_bytes += 1;
     }
 
     pub fn device_id(&self) -> u8 {
@@ -114,6 +142,11 @@ impl Ps2Mouse {
         self.buttons
     }
 
+    /// Whether the guest enabled IntelliMouse Explorer 4.0 horizontal scroll packets.
+    pub fn hwheel_enabled(&self) -> bool {
+        self.hwheel_enabled
+    }
+
     pub(crate) fn set_button_state(&mut self, button: Ps2MouseButton, pressed: bool) {
         if pressed {
             self.buttons |= button.bit();
@@ -189,6 +222,40 @@ impl Ps2Mouse {
         self.wheel = self.wheel.saturating_add(wheel);
     }
 
+    /// Inject horizontal wheel movement (positive = right).
+    ///
+    /// Only reported in stream mode once the guest enabled horizontal scrolling (see
+    /// [`Ps2Mouse::hwheel_enabled`]); otherwise the packet formats cannot represent it and the
+    /// movement is dropped.
+    pub fn inject_hwheel(&mut self, delta: i32) {
+        if !self.hwheel_enabled || self.mode != Mode::Stream || !self.reporting_enabled {
+            return;
+        }
+
+        // Horizontal packets carry a 6-bit delta in the 4th byte (-31..=32). Split large deltas
+        // like motion, with the same bound on per-call work.
+        let mut rem = delta;
+        let mut packets = 0usize;
+        while rem != 0 && packets < MAX_PACKETS_PER_INJECT {
+            if self.out.len() + 4 > MAX_OUTPUT_BYTES {
+                break;
+            }
+            let step = rem.clamp(-31, 32);
+            self.push_out(0x08 | (self.buttons & 0x07));
+            self.push_out(0);
+            self.push_out(0);
+            // Decoded by the guest as `(b & 0x20) - (b & 0x1F)`.
+            let b3 = if step > 0 {
+                0x20 | (32 - step) as u8
+            } else {
+                (-step) as u8
+            };
+            self.push_out(0x40 | b3);
+            packets += 1;
+            rem -= step;
+        }
+    }
+
     pub fn inject_button(&mut self, button: Ps2MouseButton, pressed: bool) {
         if pressed {
             self.buttons |= button.bit();
@@ -290,6 +357,7 @@ impl Ps2Mouse {
         self.reporting_enabled = false;
         // Reset any IntelliMouse extension back to the base device ID.
         self.device_id = 0x00;
+        self.hwheel_enabled = false;
         self.sample_rate_seq = [0; 3];
         self.buttons = 0;
         self.dx = 0;
@@ -316,9 +384,13 @@ impl Ps2Mouse {
                 // IntelliMouse wheel extension sequence: 200, 100, 80.
                 if self.sample_rate_seq == [200, 100, 80] {
                     self.device_id = 0x03;
+                    self.hwheel_enabled = false;
                 } else if self.sample_rate_seq == [200, 200, 80] {
                     // IntelliMouse Explorer (5-button) sequence.
                     self.device_id = 0x04;
+                } else if self.sample_rate_seq == [200, 80, 40] && self.device_id == 0x04 {
+                    // IntelliMouse Explorer 4.0 horizontal scrolling (as probed by Linux).
+                    self.hwheel_enabled = true;
                 }
             }
         }
@@ -419,7 +491,7 @@ impl Default for Ps2Mouse {
 
 impl IoSnapshot for Ps2Mouse {
     const DEVICE_ID: [u8; 4] = *b"MSE0";
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 1);
 
     fn save_state(&self) -> Vec<u8> {
         const TAG_CONFIG: u16 = 1;
@@ -427,6 +499,7 @@ impl IoSnapshot for Ps2Mouse {
         const TAG_SEQ: u16 = 3;
         const TAG_EXPECTING: u16 = 4;
         const TAG_OUTPUT: u16 = 5;
+        const TAG_HWHEEL_ENABLED: u16 = 6;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -465,6 +538,7 @@ impl IoSnapshot for Ps2Mouse {
 
         let out: Vec<u8> = self.out.iter().copied().collect();
         w.field_bytes(TAG_OUTPUT, Encoder::new().vec_u8(&out).finish());
+        w.field_bool(TAG_HWHEEL_ENABLED, self.hwheel_enabled);
 
         w.finish()
     }
@@ -475,6 +549,7 @@ impl IoSnapshot for Ps2Mouse {
         const TAG_SEQ: u16 = 3;
         const TAG_EXPECTING: u16 = 4;
         const TAG_OUTPUT: u16 = 5;
+        const TAG_HWHEEL_ENABLED: u16 = 6;
 
         let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
         r.ensure_device_major(Self::DEVICE_VERSION.major)?;
@@ -518,6 +593,10 @@ impl IoSnapshot for Ps2Mouse {
             }
         }
 
+        // Added in v1.1; only meaningful in 5-button mode.
+        self.hwheel_enabled =
+            r.bool(TAG_HWHEEL_ENABLED)?.unwrap_or(false) && self.device_id == 0x04;
+
         self.expecting_data = match r.u8(TAG_EXPECTING)?.unwrap_or(0) {
             1 => Some(ExpectingData::Resolution),
             2 => Some(ExpectingData::SampleRate),
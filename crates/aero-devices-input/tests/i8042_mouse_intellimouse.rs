@@ -0,0 +1,149 @@
+use aero_devices_input::{I8042Controller, Ps2MouseButton};
+use aero_io_snapshot::io::state::IoSnapshot;
+
+/// Send one byte to the mouse through the controller (0xD4 prefix) and return the mouse's ACK.
+fn mouse_cmd(i8042: &mut I8042Controller, byte: u8) -> u8 {
+    i8042.write_port(0x64, 0xD4);
+    i8042.write_port(0x60, byte);
+    read_aux_byte(i8042)
+}
+
+fn read_aux_byte(i8042: &mut I8042Controller) -> u8 {
+    let status = i8042.read_port(0x64);
+    assert_ne!(status & 0x01, 0, "expected a byte in the output buffer");
+    assert_ne!(
+        status & 0x20,
+        0,
+        "expected the byte to come from the AUX port"
+    );
+    i8042.read_port(0x60)
+}
+
+fn read_packet(i8042: &mut I8042Controller, len: usize) -> Vec<u8> {
+    let packet = (0..len).map(|_| read_aux_byte(i8042)).collect();
+    assert_eq!(
+        i8042.read_port(0x64) & 0x01,
+        0,
+        "unexpected bytes after the packet"
+    );
+    packet
+}
+
+fn knock(i8042: &mut I8042Controller, rates: [u8; 3]) {
+    for rate in rates {
+        assert_eq!(mouse_cmd(i8042, 0xF3), 0xFA);
+        assert_eq!(mouse_cmd(i8042, rate), 0xFA);
+    }
+}
+
+fn device_id(i8042: &mut I8042Controller) -> u8 {
+    assert_eq!(mouse_cmd(i8042, 0xF2), 0xFA);
+    read_aux_byte(i8042)
+}
+
+fn enable_reporting(i8042: &mut I8042Controller) {
+    assert_eq!(mouse_cmd(i8042, 0xF4), 0xFA);
+}
+
+#[test]
+fn without_knock_mouse_reports_id_0_and_3_byte_packets() {
+    let mut i8042 = I8042Controller::new();
+    assert_eq!(device_id(&mut i8042), 0x00);
+    enable_reporting(&mut i8042);
+
+    i8042.inject_mouse_motion(3, 0, 1);
+    assert_eq!(read_packet(&mut i8042, 3), vec![0x08, 0x03, 0x00]);
+
+    // Wheel, side buttons and horizontal scrolling have no encoding in the base format.
+    i8042.inject_mouse_motion(0, 0, 1);
+    i8042.inject_mouse_button(Ps2MouseButton::Side, true);
+    i8042.inject_mouse_hwheel(1);
+    assert_eq!(i8042.read_port(0x64) & 0x01, 0);
+}
+
+#[test]
+fn wheel_knock_switches_to_id_3_and_4_byte_packets() {
+    let mut i8042 = I8042Controller::new();
+    knock(&mut i8042, [200, 100, 80]);
+    assert_eq!(device_id(&mut i8042), 0x03);
+    enable_reporting(&mut i8042);
+
+    i8042.inject_mouse_button(Ps2MouseButton::Left, true);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x09, 0x00, 0x00, 0x00]);
+
+    // Wheel down (negative) is a signed 4-bit value in byte 4.
+    i8042.inject_mouse_motion(0, 0, -2);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x09, 0x00, 0x00, 0x0E]);
+
+    // Buttons 4/5 are still suppressed in wheel-only mode.
+    i8042.inject_mouse_button(Ps2MouseButton::Extra, true);
+    assert_eq!(i8042.read_port(0x64) & 0x01, 0);
+}
+
+#[test]
+fn explorer_knock_switches_to_id_4_with_buttons_4_and_5_in_byte_4() {
+    let mut i8042 = I8042Controller::new();
+    knock(&mut i8042, [200, 100, 80]);
+    knock(&mut i8042, [200, 200, 80]);
+    assert_eq!(device_id(&mut i8042), 0x04);
+    enable_reporting(&mut i8042);
+
+    i8042.inject_mouse_button(Ps2MouseButton::Side, true);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x10]);
+    i8042.inject_mouse_button(Ps2MouseButton::Extra, true);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x30]);
+    i8042.inject_mouse_motion(0, 0, 1);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x31]);
+
+    // Horizontal scrolling needs its own knock.
+    i8042.inject_mouse_hwheel(1);
+    assert_eq!(i8042.read_port(0x64) & 0x01, 0);
+}
+
+#[test]
+fn explorer_hwheel_knock_enables_horizontal_scroll_packets() {
+    let mut i8042 = I8042Controller::new();
+    knock(&mut i8042, [200, 200, 80]);
+    knock(&mut i8042, [200, 80, 40]);
+    assert_eq!(device_id(&mut i8042), 0x04);
+    assert!(i8042.mouse().hwheel_enabled());
+    enable_reporting(&mut i8042);
+
+    // Byte 4 = 0x40 | 6-bit value decoded as `(b & 0x20) - (b & 0x1F)`.
+    i8042.inject_mouse_hwheel(1);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x7F]);
+    i8042.inject_mouse_hwheel(-3);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x43]);
+
+    // Large deltas are split; vertical packets keep the standard Explorer layout.
+    i8042.inject_mouse_hwheel(40);
+    assert_eq!(
+        read_packet(&mut i8042, 8),
+        vec![0x08, 0x00, 0x00, 0x60, 0x08, 0x00, 0x00, 0x78]
+    );
+    i8042.inject_mouse_motion(0, 0, -1);
+    assert_eq!(read_packet(&mut i8042, 4), vec![0x08, 0x00, 0x00, 0x0F]);
+
+    // Set Defaults drops every extension.
+    assert_eq!(mouse_cmd(&mut i8042, 0xF6), 0xFA);
+    assert_eq!(device_id(&mut i8042), 0x00);
+    assert!(!i8042.mouse().hwheel_enabled());
+}
+
+#[test]
+fn negotiated_packet_format_survives_snapshot_restore() {
+    let mut i8042 = I8042Controller::new();
+    knock(&mut i8042, [200, 200, 80]);
+    knock(&mut i8042, [200, 80, 40]);
+    enable_reporting(&mut i8042);
+    let snapshot = i8042.save_state();
+
+    let mut restored = I8042Controller::new();
+    restored.load_state(&snapshot).unwrap();
+    assert_eq!(device_id(&mut restored), 0x04);
+
+    restored.inject_mouse_button(Ps2MouseButton::Extra, true);
+    assert_eq!(read_packet(&mut restored, 4), vec![0x08, 0x00, 0x00, 0x20]);
+    restored.inject_mouse_hwheel(-1);
+    assert_eq!(read_packet(&mut restored, 4), vec![0x08, 0x00, 0x00, 0x41]);
+}
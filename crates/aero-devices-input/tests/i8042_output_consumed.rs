@@ -0,0 +1,47 @@
+use aero_devices_input::I8042Controller;
+
+#[test]
+fn keyboard_output_consumed_tracks_port_0x60_reads() {
+    let mut i8042 = I8042Controller::new();
+    assert_eq!(i8042.keyboard_output_consumed(), 0);
+
+    // Set-2 make code for `A`; translation is enabled by default.
+    i8042.inject_key_scancode_bytes(&[0x1C]);
+    assert_eq!(i8042.keyboard().output_bytes_queued(), 1);
+    assert_eq!(i8042.keyboard_output_consumed(), 0);
+    assert_eq!(i8042.read_port(0x60), 0x1E);
+    assert_eq!(i8042.keyboard_output_consumed(), 1);
+
+    // The `F0` prefix is absorbed by translation and counts as read with the Set-1 break code.
+    i8042.inject_key_scancode_bytes(&[0xF0, 0x1C]);
+    assert_eq!(i8042.keyboard().output_bytes_queued(), 3);
+    assert_eq!(i8042.read_port(0x60), 0x9E);
+    assert_eq!(i8042.keyboard_output_consumed(), 3);
+
+    // Bytes written by the guest through the controller do not come from the keyboard queue.
+    i8042.write_port(0x64, 0xD2);
+    i8042.write_port(0x60, 0xAA);
+    assert_eq!(i8042.read_port(0x60), 0xAA);
+    assert_eq!(i8042.keyboard_output_consumed(), 3);
+}
+
+#[test]
+fn mouse_output_consumed_tracks_port_0x60_reads() {
+    let mut i8042 = I8042Controller::new();
+
+    // Enable data reporting; the mouse ACKs with 0xFA.
+    i8042.write_port(0x64, 0xD4);
+    i8042.write_port(0x60, 0xF4);
+    assert_eq!(i8042.read_port(0x60), 0xFA);
+    assert_eq!(i8042.mouse_output_consumed(), 1);
+
+    i8042.inject_mouse_motion(5, 0, 0);
+    assert_eq!(i8042.mouse().output_bytes_queued(), 4);
+    for _ in 0..2 {
+        i8042.read_port(0x60);
+    }
+    assert_eq!(i8042.mouse_output_consumed(), 3);
+    i8042.read_port(0x60);
+    assert_eq!(i8042.mouse_output_consumed(), 4);
+    assert_eq!(i8042.keyboard_output_consumed(), 0);
+}
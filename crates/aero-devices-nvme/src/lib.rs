@@ -23,6 +23,12 @@
 //!   backend (via [`aero_storage::VirtualDisk::discard_range`]). Backends that cannot reclaim
 //!   storage may treat discard as a no-op success.
 //!
+//! Storage robustness testing:
+//! - [`NvmeController::set_fault_injector`] attaches an [`aero_storage::StorageFaultInjector`].
+//!   Injected failures complete with the status the backend error would have produced; injected
+//!   delays hold the completion entry back until the controller's guest time passes its due time,
+//!   so several commands can be outstanding at once.
+//!
 //! Interrupts:
 //! - Legacy INTx is modelled via [`NvmeController::intx_level`].
 //! - [`NvmePciDevice`] exposes MSI and MSI-X PCI capabilities:
@@ -45,10 +51,13 @@ use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
 use aero_io_snapshot::io::storage::state::{
-    NvmeCompletionQueueState, NvmeControllerState, NvmeSubmissionQueueState,
+    NvmeCompletionQueueState, NvmeControllerState, NvmeDeferredCompletionState,
+    NvmeSubmissionQueueState,
 };
 use aero_platform::interrupts::msi::MsiTrigger;
-use aero_storage::{VirtualDisk, SECTOR_SIZE};
+use aero_storage::{
+    StorageFaultInjector, StorageRequest, StorageRequestKind, VirtualDisk, SECTOR_SIZE,
+};
 use memory::{MemoryBus, MmioHandler};
 const PAGE_SIZE: usize = 4096;
 const PCI_COMMAND_MEM_ENABLE: u16 = 1 << 1;
@@ -193,6 +202,23 @@ impl NvmeStatus {
         dnr: true,
     };
 
+    const NAMESPACE_NOT_READY: NvmeStatus = NvmeStatus {
+        sct: 0,
+        sc: 0x82,
+        dnr: false,
+    };
+
+    /// Status for a failed disk backend operation.
+    ///
+    /// A temporarily unavailable backend (e.g. a streaming disk that went offline) reports
+    /// Namespace Not Ready without DNR so the host driver retries the command.
+    fn for_disk_error(err: &aero_storage::DiskError) -> NvmeStatus {
+        match err.root() {
+            aero_storage::DiskError::BackendUnavailable => NvmeStatus::NAMESPACE_NOT_READY,
+            _ => NvmeStatus::INVALID_FIELD,
+        }
+    }
+
     fn encode_without_phase(self) -> u16 {
         let mut val: u16 = 0;
         val |= (self.sc as u16) << 1;
@@ -202,6 +228,14 @@ impl NvmeStatus {
         }
         val
     }
+
+    fn decode_without_phase(val: u16) -> NvmeStatus {
+        NvmeStatus {
+            sct: ((val >> 9) & 0x7) as u8,
+            sc: (val >> 1) as u8,
+            dnr: val & (1 << 14) != 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -278,6 +312,17 @@ struct CompletionQueue {
     irq_enabled: bool,
 }
 
+/// A completion held back by the fault injector until guest time reaches `due_ns`.
+#[derive(Debug, Clone, Copy)]
+struct DeferredCompletion {
+    due_ns: u64,
+    sqid: u16,
+    cqid: u16,
+    cid: u16,
+    status: NvmeStatus,
+    result: u32,
+}
+
 #[derive(Debug, Clone, Copy)]
 struct SubmissionQueue {
     id: u16,
@@ -320,6 +365,14 @@ pub struct NvmeController {
     /// explicit [`NvmeController::process`] step performed by the platform.
     pending_sq_tail: BTreeMap<u16, u16>,
 
+    /// Optional latency/fault injection (see [`NvmeController::set_fault_injector`]). Host
+    /// configuration, not guest state.
+    fault_injector: Option<StorageFaultInjector>,
+    /// Guest time as last reported by [`NvmeController::set_time_ns`].
+    now_ns: u64,
+    /// IO completions delayed by the fault injector, in submission order.
+    deferred: Vec<DeferredCompletion>,
+
     /// Legacy INTx derived level (asserted = true).
     ///
     /// This controller model only derives the legacy level from queue state; the PCI wrapper
@@ -365,6 +418,9 @@ impl NvmeController {
             io_sqs: HashMap::new(),
             io_cqs: HashMap::new(),
             pending_sq_tail: BTreeMap::new(),
+            fault_injector: None,
+            now_ns: 0,
+            deferred: Vec::new(),
             intx_level: false,
         }
     }
@@ -399,9 +455,41 @@ impl NvmeController {
         self.io_sqs.clear();
         self.io_cqs.clear();
         self.pending_sq_tail.clear();
+        self.deferred.clear();
         self.intx_level = false;
     }
 
+    /// Attach (or detach) a storage fault injector consulted before every disk-backed IO command
+    /// (READ, WRITE, FLUSH, WRITE ZEROES, DSM).
+    ///
+    /// Delayed commands still execute immediately, but their completion entries are only posted
+    /// by a [`NvmeController::process`] call once [`NvmeController::set_time_ns`] has advanced
+    /// past the delay. Deferred completions are part of snapshots; the injector itself is not, so a
+    /// restored controller without one releases them all on the next `process` call, as does
+    /// detaching the injector.
+    pub fn set_fault_injector(&mut self, injector: Option<StorageFaultInjector>) {
+        self.fault_injector = injector;
+    }
+
+    pub fn fault_injector(&self) -> Option<&StorageFaultInjector> {
+        self.fault_injector.as_ref()
+    }
+
+    /// Update the guest time used to schedule injected delays.
+    pub fn set_time_ns(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
+    }
+
+    /// Number of IO completions currently held back by injected delays.
+    pub fn deferred_completion_count(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Guest time at which the earliest deferred completion becomes due.
+    pub fn next_deferred_completion_ns(&self) -> Option<u64> {
+        self.deferred.iter().map(|d| d.due_ns).min()
+    }
+
     /// Construct an NVMe controller from an [`aero_storage::VirtualDisk`].
     ///
     /// Returns an error if the disk capacity is not a multiple of 512 bytes (NVMe LBAs are
@@ -706,6 +794,7 @@ impl NvmeController {
         self.io_sqs.clear();
         self.io_cqs.clear();
         self.pending_sq_tail.clear();
+        self.deferred.clear();
         self.csts = 1; // RDY
         self.refresh_intx_level();
     }
@@ -717,6 +806,7 @@ impl NvmeController {
         self.io_sqs.clear();
         self.io_cqs.clear();
         self.pending_sq_tail.clear();
+        self.deferred.clear();
         self.intx_level = false;
     }
 
@@ -820,9 +910,12 @@ impl NvmeController {
     pub fn process(&mut self, memory: &mut dyn MemoryBus) {
         if self.csts & 1 == 0 {
             self.pending_sq_tail.clear();
+            self.deferred.clear();
             return;
         }
 
+        self.post_due_completions(memory);
+
         let pending = std::mem::take(&mut self.pending_sq_tail);
         for (qid, tail) in pending {
             // Re-apply the most recently written tail value in case the queue was created after
@@ -835,6 +928,31 @@ impl NvmeController {
         self.refresh_intx_level();
     }
 
+    fn post_due_completions(&mut self, memory: &mut dyn MemoryBus) {
+        if self.deferred.is_empty() {
+            return;
+        }
+        let now_ns = self.now_ns;
+        let release_all = self.fault_injector.is_none();
+        let (mut due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.deferred)
+            .into_iter()
+            .partition(|d| release_all || d.due_ns <= now_ns);
+        self.deferred = pending;
+        // Stable sort: equal due times complete in submission order.
+        due.sort_by_key(|d| d.due_ns);
+        for d in due {
+            // The queues may have been deleted while the completion was outstanding.
+            let Some(sq) = self.io_sqs.get(&d.sqid).copied() else {
+                continue;
+            };
+            let Some(cq) = self.io_cqs.get_mut(&d.cqid) else {
+                continue;
+            };
+            post_completion(cq, &sq, d.cid, d.status, d.result, memory);
+        }
+        self.refresh_intx_level();
+    }
+
     fn process_queue_pair_admin(&mut self, memory: &mut dyn MemoryBus) {
         let mut sq = match self.admin_sq.take() {
             Some(sq) => sq,
@@ -878,13 +996,32 @@ impl NvmeController {
                 read_command(sq.base, sq.head, memory)
             };
 
-            let (status, result) = self.execute_io(cmd, memory);
+            let outcome = match (self.fault_injector.as_mut(), storage_request(&cmd)) {
+                (Some(injector), Some(req)) => injector.on_request(req),
+                _ => Default::default(),
+            };
+            let (status, result) = match outcome.error {
+                Some(err) => (NvmeStatus::for_disk_error(&err.to_disk_error()), 0),
+                None => self.execute_io(cmd, memory),
+            };
 
             {
                 let sq = self.io_sqs.get_mut(&qid).unwrap();
                 sq.head = sq.head.wrapping_add(1) % sq.size;
             }
 
+            if outcome.delay_ns != 0 {
+                self.deferred.push(DeferredCompletion {
+                    due_ns: self.now_ns.saturating_add(outcome.delay_ns),
+                    sqid: qid,
+                    cqid,
+                    cid: cmd.cid,
+                    status,
+                    result,
+                });
+                continue;
+            }
+
             let (sq_snapshot, cq) = {
                 let sq_snapshot = *self.io_sqs.get(&qid).unwrap();
                 let cq = self.io_cqs.get_mut(&cqid).unwrap();
@@ -1058,6 +1195,8 @@ impl NvmeController {
         // Clear any pending doorbell update so recreating the queue can't accidentally pick up an
         // old tail value.
         self.pending_sq_tail.remove(&qid);
+        // Deleting an SQ aborts its outstanding commands.
+        self.deferred.retain(|d| d.sqid != qid);
         // Keep derived interrupt level coherent after any queue topology changes.
         self.refresh_intx_level();
         (NvmeStatus::SUCCESS, 0)
@@ -1202,7 +1341,7 @@ impl NvmeController {
         data.resize(len, 0);
         let status = match self.disk.read_sectors(slba, &mut data) {
             Ok(()) => NvmeStatus::SUCCESS,
-            Err(_) => NvmeStatus::INVALID_FIELD,
+            Err(err) => NvmeStatus::for_disk_error(&err),
         };
         if status != NvmeStatus::SUCCESS {
             return (status, 0);
@@ -1252,7 +1391,7 @@ impl NvmeController {
 
         let status = match self.disk.write_sectors(slba, &data) {
             Ok(()) => NvmeStatus::SUCCESS,
-            Err(_) => NvmeStatus::INVALID_FIELD,
+            Err(err) => NvmeStatus::for_disk_error(&err),
         };
 
         (status, 0)
@@ -1261,7 +1400,7 @@ impl NvmeController {
     fn cmd_flush(&mut self) -> (NvmeStatus, u32) {
         let status = match self.disk.flush() {
             Ok(()) => NvmeStatus::SUCCESS,
-            Err(_) => NvmeStatus::INVALID_FIELD,
+            Err(err) => NvmeStatus::for_disk_error(&err),
         };
         (status, 0)
     }
@@ -1306,7 +1445,7 @@ impl NvmeController {
 
         let status = match self.disk.write_sectors(slba, &zeros) {
             Ok(()) => NvmeStatus::SUCCESS,
-            Err(_) => NvmeStatus::INVALID_FIELD,
+            Err(err) => NvmeStatus::for_disk_error(&err),
         };
         (status, 0)
     }
@@ -1544,6 +1683,8 @@ impl IoSnapshot for NvmeController {
         // snapshot state struct.
         //
         // This controller processes commands synchronously, so there is no meaningful in-flight state.
+        // Completions deferred by an attached fault injector are saved so the guest still receives
+        // them after restore.
         let state = NvmeControllerState {
             cap: self.cap,
             vs: self.vs,
@@ -1602,6 +1743,19 @@ impl IoSnapshot for NvmeController {
                 .collect(),
             intx_level: self.intx_level,
             in_flight: Vec::new(),
+            now_ns: self.now_ns,
+            deferred_completions: self
+                .deferred
+                .iter()
+                .map(|d| NvmeDeferredCompletionState {
+                    due_ns: d.due_ns,
+                    sqid: d.sqid,
+                    cqid: d.cqid,
+                    cid: d.cid,
+                    status: d.status.encode_without_phase(),
+                    result: d.result,
+                })
+                .collect(),
         };
 
         state.save_state()
@@ -1789,6 +1943,20 @@ impl IoSnapshot for NvmeController {
         self.feature_num_io_cqs = self.feature_num_io_cqs.min(max_io_queues_0based);
 
         self.pending_sq_tail.clear();
+        // Completions for queues missing from the snapshot are dropped when they come due.
+        self.now_ns = state.now_ns;
+        self.deferred = state
+            .deferred_completions
+            .iter()
+            .map(|d| DeferredCompletion {
+                due_ns: d.due_ns,
+                sqid: d.sqid,
+                cqid: d.cqid,
+                cid: d.cid,
+                status: NvmeStatus::decode_without_phase(d.status),
+                result: d.result,
+            })
+            .collect();
         if self.csts & 1 != 0 {
             // Doorbell writes are tracked in `pending_sq_tail` so DMA processing can be deferred
             // out of the MMIO handler. The snapshot state does not include the pending doorbell
@@ -1813,6 +1981,21 @@ impl IoSnapshot for NvmeController {
     }
 }
 
+/// The disk request an IO command will issue, for fault injection purposes.
+fn storage_request(cmd: &NvmeCommand) -> Option<StorageRequest> {
+    let lba = u64::from(cmd.cdw10) | (u64::from(cmd.cdw11) << 32);
+    let sectors = u64::from(cmd.cdw12 & 0xffff) + 1;
+    let (kind, lba, sectors) = match cmd.opc {
+        0x00 => (StorageRequestKind::Flush, 0, 0),
+        0x01 | 0x08 => (StorageRequestKind::Write, lba, sectors),
+        0x02 => (StorageRequestKind::Read, lba, sectors),
+        // DSM ranges live in guest memory; treat deallocation as a whole-device write.
+        0x09 => (StorageRequestKind::Write, 0, u64::MAX),
+        _ => return None,
+    };
+    Some(StorageRequest { kind, lba, sectors })
+}
+
 fn read_command(sq_base: u64, head: u16, memory: &mut dyn MemoryBus) -> NvmeCommand {
     let mut bytes = [0u8; 64];
     let addr = sq_base + head as u64 * 64;
@@ -2827,6 +3010,7 @@ mod tests {
                 AeroSparseConfig {
                     disk_size_bytes: capacity_bytes,
                     block_size_bytes: 1024 * 1024,
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -3519,6 +3703,7 @@ mod tests {
             AeroSparseConfig {
                 disk_size_bytes: capacity_bytes,
                 block_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
         )
         .unwrap();
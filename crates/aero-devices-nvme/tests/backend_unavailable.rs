@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use aero_devices_nvme::NvmeController;
+use aero_storage::{
+    DiskError as StorageDiskError, Result as StorageResult, VirtualDisk, SECTOR_SIZE,
+};
+use memory::MemoryBus;
+
+// Completion status encodings (without phase).
+const NVME_STATUS_SUCCESS: u16 = 0x0000;
+const NVME_STATUS_INVALID_FIELD: u16 = 0x4004;
+// Namespace Not Ready (SCT 0, SC 0x82) with DNR clear.
+const NVME_STATUS_NAMESPACE_NOT_READY: u16 = 0x0104;
+
+struct TestMem {
+    buf: Vec<u8>,
+}
+
+impl MemoryBus for TestMem {
+    fn read_physical(&mut self, paddr: u64, out: &mut [u8]) {
+        let start = paddr as usize;
+        out.copy_from_slice(&self.buf[start..start + out.len()]);
+    }
+
+    fn write_physical(&mut self, paddr: u64, data: &[u8]) {
+        let start = paddr as usize;
+        self.buf[start..start + data.len()].copy_from_slice(data);
+    }
+}
+
+/// A disk whose reads fail while `offline` is set, like a streaming disk without network.
+struct FlakyDisk {
+    data: Vec<u8>,
+    offline: Arc<AtomicBool>,
+    error: fn() -> StorageDiskError,
+}
+
+impl VirtualDisk for FlakyDisk {
+    fn capacity_bytes(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> StorageResult<()> {
+        if self.offline.load(Ordering::SeqCst) {
+            return Err((self.error)());
+        }
+        let start = offset as usize;
+        buf.copy_from_slice(&self.data[start..start + buf.len()]);
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> StorageResult<()> {
+        let start = offset as usize;
+        self.data[start..start + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> StorageResult<()> {
+        Ok(())
+    }
+}
+
+fn build_command(opc: u8, cid: u16) -> [u8; 64] {
+    let mut cmd = [0u8; 64];
+    cmd[0] = opc;
+    cmd[2..4].copy_from_slice(&cid.to_le_bytes());
+    cmd
+}
+
+fn set_u32(cmd: &mut [u8; 64], offset: usize, val: u32) {
+    cmd[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+fn set_u64(cmd: &mut [u8; 64], offset: usize, val: u64) {
+    cmd[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
+}
+
+fn read_cqe(mem: &mut TestMem, cq_base: u64, index: u16) -> (u16, u16) {
+    let mut bytes = [0u8; 16];
+    mem.read_physical(cq_base + u64::from(index) * 16, &mut bytes);
+    let dw3 = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+    ((dw3 & 0xffff) as u16, (dw3 >> 16) as u16)
+}
+
+const ASQ: u64 = 0x10000;
+const ACQ: u64 = 0x20000;
+const IO_CQ: u64 = 0x40000;
+const IO_SQ: u64 = 0x50000;
+const READ_BUF: u64 = 0x60000;
+
+fn setup(error: fn() -> StorageDiskError) -> (NvmeController, TestMem, Arc<AtomicBool>) {
+    let offline = Arc::new(AtomicBool::new(false));
+    let mut data = vec![0u8; 64 * SECTOR_SIZE];
+    data[..SECTOR_SIZE].fill(0x5A);
+    let disk = FlakyDisk {
+        data,
+        offline: offline.clone(),
+        error,
+    };
+    let mut ctrl = NvmeController::try_new_from_aero_storage(disk).unwrap();
+    let mut mem = TestMem {
+        buf: vec![0; 1024 * 1024],
+    };
+
+    ctrl.mmio_write(0x0024, 4, 0x000f_000f);
+    ctrl.mmio_write(0x0028, 8, ASQ);
+    ctrl.mmio_write(0x0030, 8, ACQ);
+    ctrl.mmio_write(0x0014, 4, 1);
+
+    // Create IO CQ (qid=1, size=16, PC+IEN), then IO SQ (qid=1, size=16, cqid=1).
+    let mut cmd = build_command(0x05, 1);
+    set_u64(&mut cmd, 24, IO_CQ);
+    set_u32(&mut cmd, 40, (15u32 << 16) | 1);
+    set_u32(&mut cmd, 44, 0x3);
+    mem.write_physical(ASQ, &cmd);
+    ctrl.mmio_write(0x1000, 4, 1);
+    ctrl.process(&mut mem);
+
+    let mut cmd = build_command(0x01, 2);
+    set_u64(&mut cmd, 24, IO_SQ);
+    set_u32(&mut cmd, 40, (15u32 << 16) | 1);
+    set_u32(&mut cmd, 44, 1);
+    mem.write_physical(ASQ + 64, &cmd);
+    ctrl.mmio_write(0x1000, 4, 2);
+    ctrl.process(&mut mem);
+
+    (ctrl, mem, offline)
+}
+
+/// Submits a one-sector READ of LBA 0 in IO SQ slot `slot` and returns its completion status.
+fn read_lba0(ctrl: &mut NvmeController, mem: &mut TestMem, slot: u16) -> u16 {
+    let cid = 0x100 + slot;
+    let mut cmd = build_command(0x02, cid);
+    set_u32(&mut cmd, 4, 1); // NSID
+    set_u64(&mut cmd, 24, READ_BUF);
+    mem.write_physical(IO_SQ + u64::from(slot) * 64, &cmd);
+    ctrl.mmio_write(0x1008, 4, u64::from(slot) + 1);
+    ctrl.process(mem);
+
+    let (got_cid, status) = read_cqe(mem, IO_CQ, slot);
+    assert_eq!(got_cid, cid);
+    status & !0x1
+}
+
+#[test]
+fn unavailable_backend_reports_retryable_namespace_not_ready() {
+    let (mut ctrl, mut mem, offline) = setup(|| StorageDiskError::BackendUnavailable);
+
+    offline.store(true, Ordering::SeqCst);
+    assert_eq!(
+        read_lba0(&mut ctrl, &mut mem, 0),
+        NVME_STATUS_NAMESPACE_NOT_READY
+    );
+
+    // The host driver retries once the backend is reachable again.
+    offline.store(false, Ordering::SeqCst);
+    assert_eq!(read_lba0(&mut ctrl, &mut mem, 1), NVME_STATUS_SUCCESS);
+    let mut sector = vec![0u8; SECTOR_SIZE];
+    mem.read_physical(READ_BUF, &mut sector);
+    assert_eq!(sector, vec![0x5A; SECTOR_SIZE]);
+}
+
+#[test]
+fn other_backend_errors_are_not_retryable() {
+    let (mut ctrl, mut mem, offline) = setup(|| StorageDiskError::Io("broken".to_string()));
+
+    offline.store(true, Ordering::SeqCst);
+    assert_eq!(read_lba0(&mut ctrl, &mut mem, 0), NVME_STATUS_INVALID_FIELD);
+}
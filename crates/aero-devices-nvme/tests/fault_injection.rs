@@ -0,0 +1,221 @@
+use aero_devices_nvme::NvmeController;
+use aero_io_snapshot::io::state::IoSnapshot;
+use aero_storage::{
+    MemBackend, RawDisk, StorageFaultDelay, StorageFaultError, StorageFaultInjector,
+    StorageFaultPolicy, SECTOR_SIZE,
+};
+use memory::MemoryBus;
+
+const NVME_STATUS_SUCCESS: u16 = 0x0000;
+// Namespace Not Ready (SCT 0, SC 0x82) with DNR clear.
+const NVME_STATUS_NAMESPACE_NOT_READY: u16 = 0x0104;
+
+const DELAY_NS: u64 = 5_000_000;
+
+struct TestMem {
+    buf: Vec<u8>,
+}
+
+impl MemoryBus for TestMem {
+    fn read_physical(&mut self, paddr: u64, out: &mut [u8]) {
+        let start = paddr as usize;
+        out.copy_from_slice(&self.buf[start..start + out.len()]);
+    }
+
+    fn write_physical(&mut self, paddr: u64, data: &[u8]) {
+        let start = paddr as usize;
+        self.buf[start..start + data.len()].copy_from_slice(data);
+    }
+}
+
+fn build_command(opc: u8, cid: u16) -> [u8; 64] {
+    let mut cmd = [0u8; 64];
+    cmd[0] = opc;
+    cmd[2..4].copy_from_slice(&cid.to_le_bytes());
+    cmd
+}
+
+fn set_u32(cmd: &mut [u8; 64], offset: usize, val: u32) {
+    cmd[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+fn set_u64(cmd: &mut [u8; 64], offset: usize, val: u64) {
+    cmd[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
+}
+
+/// Returns `(cid, status_with_phase)` of the completion entry at `index`.
+fn read_cqe(mem: &mut TestMem, cq_base: u64, index: u16) -> (u16, u16) {
+    let mut bytes = [0u8; 16];
+    mem.read_physical(cq_base + u64::from(index) * 16, &mut bytes);
+    let dw3 = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+    ((dw3 & 0xffff) as u16, (dw3 >> 16) as u16)
+}
+
+const ASQ: u64 = 0x10000;
+const ACQ: u64 = 0x20000;
+const IO_CQ: u64 = 0x40000;
+const IO_SQ: u64 = 0x50000;
+const READ_BUF: u64 = 0x60000;
+
+fn setup() -> (NvmeController, TestMem) {
+    let disk = RawDisk::create(MemBackend::new(), 64 * SECTOR_SIZE as u64).unwrap();
+    let mut ctrl = NvmeController::try_new_from_aero_storage(disk).unwrap();
+    let mut mem = TestMem {
+        buf: vec![0; 1024 * 1024],
+    };
+
+    ctrl.mmio_write(0x0024, 4, 0x000f_000f);
+    ctrl.mmio_write(0x0028, 8, ASQ);
+    ctrl.mmio_write(0x0030, 8, ACQ);
+    ctrl.mmio_write(0x0014, 4, 1);
+
+    // Create IO CQ (qid=1, size=16, PC+IEN), then IO SQ (qid=1, size=16, cqid=1).
+    let mut cmd = build_command(0x05, 1);
+    set_u64(&mut cmd, 24, IO_CQ);
+    set_u32(&mut cmd, 40, (15u32 << 16) | 1);
+    set_u32(&mut cmd, 44, 0x3);
+    mem.write_physical(ASQ, &cmd);
+    ctrl.mmio_write(0x1000, 4, 1);
+    ctrl.process(&mut mem);
+
+    let mut cmd = build_command(0x01, 2);
+    set_u64(&mut cmd, 24, IO_SQ);
+    set_u32(&mut cmd, 40, (15u32 << 16) | 1);
+    set_u32(&mut cmd, 44, 1);
+    mem.write_physical(ASQ + 64, &cmd);
+    ctrl.mmio_write(0x1000, 4, 2);
+    ctrl.process(&mut mem);
+
+    // Consume both admin completions so INTx only reflects IO queue activity.
+    ctrl.mmio_write(0x1004, 4, 2);
+
+    (ctrl, mem)
+}
+
+/// Queues one-sector READs of LBA `slot` in IO SQ slots `0..count` and rings the doorbell once.
+fn submit_reads(ctrl: &mut NvmeController, mem: &mut TestMem, count: u16) {
+    for slot in 0..count {
+        let mut cmd = build_command(0x02, 0x100 + slot);
+        set_u32(&mut cmd, 4, 1); // NSID
+        set_u64(
+            &mut cmd,
+            24,
+            READ_BUF + u64::from(slot) * SECTOR_SIZE as u64,
+        );
+        set_u32(&mut cmd, 40, u32::from(slot)); // SLBA
+        mem.write_physical(IO_SQ + u64::from(slot) * 64, &cmd);
+    }
+    ctrl.mmio_write(0x1008, 4, u64::from(count));
+}
+
+#[test]
+fn delayed_reads_stay_outstanding_together_until_guest_time_passes() {
+    let (mut ctrl, mut mem) = setup();
+    ctrl.set_fault_injector(Some(StorageFaultInjector::new(StorageFaultPolicy {
+        delay: StorageFaultDelay::Fixed { ns: DELAY_NS },
+        ..Default::default()
+    })));
+
+    submit_reads(&mut ctrl, &mut mem, 8);
+    ctrl.process(&mut mem);
+
+    // All eight commands were consumed from the SQ, but none has completed yet.
+    assert_eq!(ctrl.deferred_completion_count(), 8);
+    assert_eq!(ctrl.next_deferred_completion_ns(), Some(DELAY_NS));
+    assert_eq!(read_cqe(&mut mem, IO_CQ, 0), (0, 0));
+    assert!(!ctrl.intx_level);
+
+    ctrl.set_time_ns(DELAY_NS - 1);
+    ctrl.process(&mut mem);
+    assert_eq!(ctrl.deferred_completion_count(), 8);
+
+    ctrl.set_time_ns(DELAY_NS);
+    ctrl.process(&mut mem);
+    assert_eq!(ctrl.deferred_completion_count(), 0);
+    for slot in 0..8 {
+        let (cid, status) = read_cqe(&mut mem, IO_CQ, slot);
+        assert_eq!(cid, 0x100 + slot);
+        assert_eq!(status & !0x1, NVME_STATUS_SUCCESS);
+        assert_eq!(status & 0x1, 1, "phase tag");
+    }
+    assert!(ctrl.intx_level);
+
+    let counters = ctrl.fault_injector().unwrap().counters();
+    assert_eq!(counters.requests, 8);
+    assert_eq!(counters.delayed, 8);
+    assert_eq!(counters.delay_ns_total, 8 * DELAY_NS);
+}
+
+#[test]
+fn every_nth_read_fails_with_the_injected_error() {
+    let (mut ctrl, mut mem) = setup();
+    ctrl.set_fault_injector(Some(StorageFaultInjector::new(StorageFaultPolicy {
+        fail_every_nth: Some((3, StorageFaultError::BackendUnavailable)),
+        ..Default::default()
+    })));
+
+    submit_reads(&mut ctrl, &mut mem, 6);
+    ctrl.process(&mut mem);
+
+    let statuses: Vec<u16> = (0..6)
+        .map(|slot| read_cqe(&mut mem, IO_CQ, slot).1 & !0x1)
+        .collect();
+    let fail = NVME_STATUS_NAMESPACE_NOT_READY;
+    let ok = NVME_STATUS_SUCCESS;
+    assert_eq!(statuses, vec![ok, ok, fail, ok, ok, fail]);
+    assert_eq!(ctrl.fault_injector().unwrap().counters().failed_nth, 2);
+}
+
+#[test]
+fn detaching_the_injector_releases_deferred_completions() {
+    let (mut ctrl, mut mem) = setup();
+    ctrl.set_fault_injector(Some(StorageFaultInjector::new(StorageFaultPolicy {
+        delay: StorageFaultDelay::Fixed { ns: DELAY_NS },
+        ..Default::default()
+    })));
+
+    submit_reads(&mut ctrl, &mut mem, 2);
+    ctrl.process(&mut mem);
+    assert_eq!(ctrl.deferred_completion_count(), 2);
+
+    ctrl.set_fault_injector(None);
+    ctrl.process(&mut mem);
+    assert_eq!(ctrl.deferred_completion_count(), 0);
+    assert_eq!(read_cqe(&mut mem, IO_CQ, 1).0, 0x101);
+}
+
+#[test]
+fn deferred_completions_survive_snapshot_restore() {
+    let (mut ctrl, mut mem) = setup();
+    let injector = || {
+        Some(StorageFaultInjector::new(StorageFaultPolicy {
+            delay: StorageFaultDelay::Fixed { ns: DELAY_NS },
+            ..Default::default()
+        }))
+    };
+    ctrl.set_fault_injector(injector());
+
+    submit_reads(&mut ctrl, &mut mem, 2);
+    ctrl.process(&mut mem);
+    assert_eq!(ctrl.deferred_completion_count(), 2);
+    let snap = ctrl.save_state();
+
+    // With the injector re-attached, the restored completions still wait for their due time.
+    let (mut restored, _mem) = setup();
+    restored.load_state(&snap).unwrap();
+    restored.set_fault_injector(injector());
+    assert_eq!(restored.deferred_completion_count(), 2);
+    assert_eq!(restored.next_deferred_completion_ns(), Some(DELAY_NS));
+    restored.process(&mut mem);
+    assert_eq!(read_cqe(&mut mem, IO_CQ, 0), (0, 0));
+
+    restored.set_time_ns(DELAY_NS);
+    restored.process(&mut mem);
+    assert_eq!(restored.deferred_completion_count(), 0);
+    for slot in 0..2 {
+        let (cid, status) = read_cqe(&mut mem, IO_CQ, slot);
+        assert_eq!(cid, 0x100 + slot);
+        assert_eq!(status, NVME_STATUS_SUCCESS | 0x1);
+    }
+    assert!(restored.intx_level);
+}
@@ -4,25 +4,31 @@
 //! command list engine, PRDT-based DMA into guest memory, and interrupts.
 //!
 //! This module implements enough of the AHCI 1.x programming model for early boot:
-//! - HBA memory registers (CAP/GHC/IS/PI/VS)
-//! - Per-port registers (CLB/FB/IS/IE/CMD/TFD/SIG/SSTS/CI)
+//! - HBA memory registers (CAP/GHC/IS/PI/VS). CAP/CAP2 advertise only what is implemented: no
+//!   NCQ, port multipliers, staggered spin-up or link power management.
+//! - Per-port registers (CLB/FB/IS/IE/CMD/TFD/SIG/SSTS/CI), including the PxCMD ST/FRE/CR/FR
+//!   interlocks, COMRESET with PxSERR diagnostics, and PhyRdy/presence change reporting on
+//!   hot attach/detach (see [`AhciController::hot_attach_drive`])
 //! - Command list parsing (command header + command table + PRDT)
 //! - ATA commands: IDENTIFY, READ/WRITE DMA (28-bit + EXT), READ/WRITE SECTORS (PIO 28-bit + EXT),
 //!   FLUSH CACHE(_EXT), SET FEATURES
+//! - Optional storage fault injection with deferred completion (see
+//!   [`AhciController::set_fault_injector`])
 
 use std::fmt;
 use std::io;
 
 use crate::ata::{
-    AtaDrive, ATA_CMD_FLUSH_CACHE, ATA_CMD_FLUSH_CACHE_EXT, ATA_CMD_IDENTIFY, ATA_CMD_READ_DMA,
-    ATA_CMD_READ_DMA_EXT, ATA_CMD_READ_SECTORS, ATA_CMD_READ_SECTORS_EXT, ATA_CMD_SET_FEATURES,
-    ATA_CMD_WRITE_DMA, ATA_CMD_WRITE_DMA_EXT, ATA_CMD_WRITE_SECTORS, ATA_CMD_WRITE_SECTORS_EXT,
-    ATA_ERROR_ABRT, ATA_STATUS_BSY, ATA_STATUS_DRDY, ATA_STATUS_DSC, ATA_STATUS_ERR,
+    ata_error_for_io_error, clone_io_error, AtaDrive, ATA_CMD_FLUSH_CACHE, ATA_CMD_FLUSH_CACHE_EXT,
+    ATA_CMD_IDENTIFY, ATA_CMD_READ_DMA, ATA_CMD_READ_DMA_EXT, ATA_CMD_READ_SECTORS,
+    ATA_CMD_READ_SECTORS_EXT, ATA_CMD_SET_FEATURES, ATA_CMD_WRITE_DMA, ATA_CMD_WRITE_DMA_EXT,
+    ATA_CMD_WRITE_SECTORS, ATA_CMD_WRITE_SECTORS_EXT, ATA_ERROR_ABRT, ATA_STATUS_BSY,
+    ATA_STATUS_DRDY, ATA_STATUS_DRQ, ATA_STATUS_DSC, ATA_STATUS_ERR,
 };
 use aero_devices::irq::IrqLine;
 use aero_io_snapshot::io::state::{IoSnapshot, SnapshotResult, SnapshotVersion};
 use aero_io_snapshot::io::storage::state::{AhciControllerState, AhciHbaState, AhciPortState};
-use aero_storage::SECTOR_SIZE;
+use aero_storage::{StorageFaultInjector, StorageRequest, StorageRequestKind, SECTOR_SIZE};
 use memory::MemoryBus;
 
 const HBA_REG_CAP: u64 = 0x00;
@@ -51,6 +57,14 @@ const PORT_REG_SERR: u64 = 0x30;
 const PORT_REG_SACT: u64 = 0x34;
 const PORT_REG_CI: u64 = 0x38;
 
+// CAP bits. Capabilities the model does not implement (SNCQ, SSS, SPM, SALP, ...) stay clear.
+const CAP_S64A: u32 = 1 << 31;
+const CAP_SCLO: u32 = 1 << 24;
+/// Interface speed support: Gen1 (1.5 Gbps), matching the PxSSTS.SPD we report.
+const CAP_ISS_GEN1: u32 = 1 << 20;
+/// AHCI mode only: GHC.AE is read-only one.
+const CAP_SAM: u32 = 1 << 18;
+
 const GHC_HR: u32 = 1 << 0;
 const GHC_IE: u32 = 1 << 1;
 const GHC_AE: u32 = 1 << 31;
@@ -66,11 +80,28 @@ const BOHC_OOC: u32 = 1 << 3;
 const _BOHC_BB: u32 = 1 << 4;
 
 const PORT_CMD_ST: u32 = 1 << 0;
+const PORT_CMD_SUD: u32 = 1 << 1;
+const PORT_CMD_POD: u32 = 1 << 2;
+const PORT_CMD_CLO: u32 = 1 << 3;
 const PORT_CMD_FRE: u32 = 1 << 4;
+const PORT_CMD_CCS_SHIFT: u32 = 8;
+const PORT_CMD_CCS_MASK: u32 = 0x1F << PORT_CMD_CCS_SHIFT;
 const PORT_CMD_FR: u32 = 1 << 14;
 const PORT_CMD_CR: u32 = 1 << 15;
+const PORT_CMD_HPCP: u32 = 1 << 18;
+const PORT_CMD_ATAPI: u32 = 1 << 24;
+const PORT_CMD_DLAE: u32 = 1 << 25;
+/// PxCMD bits hardwired to one: SUD (CAP.SSS is clear), POD (no cold presence detection) and HPCP
+/// (ports support hot attach/detach).
+const PORT_CMD_HARDWIRED: u32 = PORT_CMD_SUD | PORT_CMD_POD | PORT_CMD_HPCP;
+/// Plain read/write PxCMD bits (ST, FRE and CLO have dedicated handling).
+const PORT_CMD_RW: u32 = PORT_CMD_ATAPI | PORT_CMD_DLAE;
 
 const PORT_IS_DHRS: u32 = 1 << 0;
+/// Port Connect Change Status. Read-only; mirrors PxSERR.DIAG.X.
+const PORT_IS_PCS: u32 = 1 << 6;
+/// PhyRdy Change Status. Read-only; mirrors PxSERR.DIAG.N.
+const PORT_IS_PRCS: u32 = 1 << 22;
 const PORT_IS_TFES: u32 = 1 << 30;
 
 /// SATA drive signature (PxSIG) for an ATA device.
@@ -97,6 +128,7 @@ const SCTL_DET_DISABLE_ALT: u32 = 2;
 // reset/abort events to latch some error condition.
 const SERR_ERR_PROTOCOL: u32 = 1 << 4;
 const SERR_DIAG_PHYRDY_CHANGE: u32 = 1 << 16;
+const SERR_DIAG_EXCHANGED: u32 = 1 << 26;
 
 #[derive(Debug, Clone, Copy)]
 struct HbaRegs {
@@ -113,10 +145,8 @@ impl HbaRegs {
         let np = (num_ports.saturating_sub(1) as u32) & 0x1F;
         // CAP.NCS is number of command slots minus 1.
         let ncs = 31u32 << 8; // 32 slots
-                              // CAP.S64A indicates 64-bit addressing is supported.
-        let s64a = 1u32 << 31;
         Self {
-            cap: np | ncs | s64a,
+            cap: np | ncs | CAP_S64A | CAP_SCLO | CAP_ISS_GEN1 | CAP_SAM,
             // QEMU/ICH9-style: controller comes up in AHCI mode with AE set.
             ghc: GHC_AE,
             // Advertise BIOS/OS handoff (BOHC) capability since we expose the register.
@@ -164,7 +194,7 @@ impl PortRegs {
             fb: 0,
             is: 0,
             ie: 0,
-            cmd: 0,
+            cmd: PORT_CMD_HARDWIRED,
             tfd,
             sig,
             ssts,
@@ -201,6 +231,30 @@ impl PortRegs {
     fn sctl_det(&self) -> u32 {
         self.sctl & SCTL_DET_MASK
     }
+
+    fn phy_ready(&self) -> bool {
+        (self.ssts & SSTS_DET_MASK) == SSTS_DET_DEVICE_PRESENT_PHY
+    }
+
+    /// Refresh PxIS.PRCS/PCS from the PxSERR.DIAG.N/X bits they mirror.
+    fn sync_serr_status(&mut self) {
+        self.is &= !(PORT_IS_PRCS | PORT_IS_PCS);
+        if self.serr & SERR_DIAG_PHYRDY_CHANGE != 0 {
+            self.is |= PORT_IS_PRCS;
+        }
+        if self.serr & SERR_DIAG_EXCHANGED != 0 {
+            self.is |= PORT_IS_PCS;
+        }
+    }
+}
+
+/// A command that has executed but whose completion is held back by an injected delay.
+#[derive(Debug)]
+struct DeferredCommand {
+    due_ns: u64,
+    slot: usize,
+    /// Bytes transferred, or the error to report.
+    result: io::Result<u32>,
 }
 
 #[derive(Debug)]
@@ -208,6 +262,11 @@ struct AhciPort {
     present: bool,
     regs: PortRegs,
     drive: Option<AtaDrive>,
+    deferred: Vec<DeferredCommand>,
+    /// The device's signature D2H Register FIS from the last link-up still has to be posted to
+    /// the FIS receive area (done by the next [`AhciController::process`], which has memory
+    /// access). Host-side state like `deferred`; not captured in snapshots.
+    signature_fis_pending: bool,
 }
 
 impl AhciPort {
@@ -216,9 +275,46 @@ impl AhciPort {
             present: false,
             regs: PortRegs::new(false),
             drive: None,
+            deferred: Vec::new(),
+            signature_fis_pending: false,
         }
     }
 
+    /// Establish the link with the attached device after COMRESET, re-enable or hot attach.
+    ///
+    /// The PhyRdy transition and the COMINIT from the device latch PxSERR.DIAG.N/X, and the
+    /// device's signature D2H Register FIS updates PxSIG/PxTFD and sets PxIS.DHRS.
+    fn link_up(&mut self) {
+        self.regs.ssts = SSTS_IPM_ACTIVE | SSTS_SPD_GEN1 | SSTS_DET_DEVICE_PRESENT_PHY;
+        self.regs.sig = SATA_SIG_ATA;
+        self.regs.tfd = u32::from(ATA_STATUS_DRDY | ATA_STATUS_DSC);
+        self.regs.serr |= SERR_DIAG_PHYRDY_CHANGE | SERR_DIAG_EXCHANGED;
+        self.regs.is |= PORT_IS_DHRS;
+        self.regs.sync_serr_status();
+        // The FIS is only stored when FIS receive is enabled.
+        self.signature_fis_pending = self.regs.fis_receive_enabled();
+    }
+
+    /// Link state of an empty port after COMRESET or re-enable.
+    fn link_down_empty(&mut self) {
+        self.regs.ssts = SSTS_DET_NO_DEVICE;
+        self.regs.sig = 0;
+        self.regs.tfd = 0;
+    }
+
+    /// PxCI bits of commands whose completion is deferred.
+    fn deferred_slots(&self) -> u32 {
+        self.deferred
+            .iter()
+            .fold(0, |mask, cmd| mask | (1 << cmd.slot))
+    }
+
+    /// Drop deferred commands along with their PxCI bits (port stop/reset).
+    fn abort_deferred(&mut self) {
+        self.regs.ci &= !self.deferred_slots();
+        self.deferred.clear();
+    }
+
     fn attach_drive(&mut self, drive: AtaDrive) {
         self.drive = Some(drive);
         if !self.present {
@@ -231,15 +327,59 @@ impl AhciPort {
     fn clear_drive(&mut self) {
         self.drive = None;
         self.present = false;
+        self.deferred.clear();
+        self.signature_fis_pending = false;
         self.regs = PortRegs::new(false);
         self.regs.update_running_bits();
     }
+
+    fn hot_attach(&mut self, drive: AtaDrive) {
+        self.drive = Some(drive);
+        if self.present {
+            return;
+        }
+        self.present = true;
+        match self.regs.sctl_det() {
+            // The link comes up once software releases COMRESET / re-enables the interface.
+            SCTL_DET_COMRESET => self.regs.ssts = SSTS_DET_DEVICE_PRESENT_NO_PHY,
+            SCTL_DET_DISABLE | SCTL_DET_DISABLE_ALT => {}
+            _ => self.link_up(),
+        }
+    }
+
+    fn hot_detach(&mut self) {
+        self.drive = None;
+        if !self.present {
+            return;
+        }
+        self.present = false;
+        self.abort_deferred();
+        self.signature_fis_pending = false;
+        if self.regs.phy_ready() {
+            self.regs.serr |= SERR_DIAG_PHYRDY_CHANGE;
+        }
+        self.regs.serr |= SERR_DIAG_EXCHANGED;
+        self.regs.sync_serr_status();
+        if matches!(
+            self.regs.sctl_det(),
+            SCTL_DET_DISABLE | SCTL_DET_DISABLE_ALT
+        ) {
+            // PxSSTS keeps reporting the offline PHY.
+            self.regs.sig = 0;
+            self.regs.tfd = 0;
+        } else {
+            self.link_down_empty();
+        }
+    }
 }
 
 pub struct AhciController {
     hba: HbaRegs,
     ports: Vec<AhciPort>,
     irq: Box<dyn IrqLine>,
+    fault_injector: Option<StorageFaultInjector>,
+    /// Guest time in nanoseconds, as last reported via [`AhciController::set_time_ns`].
+    now_ns: u64,
 }
 
 impl AhciController {
@@ -249,9 +389,46 @@ impl AhciController {
             hba: HbaRegs::new(num_ports),
             ports: (0..num_ports).map(|_| AhciPort::new()).collect(),
             irq,
+            fault_injector: None,
+            now_ns: 0,
         }
     }
 
+    /// Install (or remove) a storage fault injector consulted before each disk command.
+    ///
+    /// Commands that receive an injected delay execute immediately but complete (PxCI bit
+    /// cleared, D2H FIS posted, interrupt raised) from the first [`AhciController::process`]
+    /// call at or after their due time, so several command slots can be outstanding at once.
+    /// Removing the injector completes deferred commands on the next `process` call.
+    ///
+    /// Deferred completions are host-side state and are not captured in snapshots: their PxCI
+    /// bits are, so a restored controller executes those commands again.
+    pub fn set_fault_injector(&mut self, injector: Option<StorageFaultInjector>) {
+        self.fault_injector = injector;
+    }
+
+    pub fn fault_injector(&self) -> Option<&StorageFaultInjector> {
+        self.fault_injector.as_ref()
+    }
+
+    /// Advance the controller's view of guest time, used to schedule deferred completions.
+    pub fn set_time_ns(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
+    }
+
+    /// Number of commands whose completion is currently deferred.
+    pub fn deferred_command_count(&self) -> usize {
+        self.ports.iter().map(|port| port.deferred.len()).sum()
+    }
+
+    /// Earliest guest time at which a deferred command completes, if any are outstanding.
+    pub fn next_deferred_completion_ns(&self) -> Option<u64> {
+        self.ports
+            .iter()
+            .flat_map(|port| port.deferred.iter().map(|cmd| cmd.due_ns))
+            .min()
+    }
+
     pub fn attach_drive(&mut self, port: usize, drive: AtaDrive) {
         self.ports[port].attach_drive(drive);
     }
@@ -260,6 +437,43 @@ impl AhciController {
         self.ports[port].clear_drive();
     }
 
+    /// Hot-plug `drive` into `port` while the guest is running.
+    ///
+    /// Unlike [`AhciController::attach_drive`], which models a drive present since power-on, the
+    /// port's programmed registers are kept and the link comes up as it would for a real hot
+    /// plug: PxSERR.DIAG.N/X latch (raising PxIS.PRCS/PCS) and the device's signature FIS is
+    /// received. Attaching to a port that already has a device only swaps the backend.
+    pub fn hot_attach_drive(&mut self, port: usize, drive: AtaDrive) {
+        self.ports[port].hot_attach(drive);
+        self.update_irq();
+    }
+
+    /// Surprise-remove the drive on `port` while the guest is running.
+    ///
+    /// The link drops (PxSSTS.DET=0), PxSERR.DIAG.N/X latch (raising PxIS.PRCS/PCS), and commands
+    /// whose completion is deferred are dropped.
+    pub fn hot_detach_drive(&mut self, port: usize) {
+        self.ports[port].hot_detach();
+        self.update_irq();
+    }
+
+    /// Signal the guest that the device on `port` changed (e.g. its capacity grew).
+    ///
+    /// Latches PxSERR.DIAG.X ("exchanged") and the PxIS.PCS bit that mirrors it. Drivers such as
+    /// `storahci.sys` respond by re-enumerating the port, which re-reads IDENTIFY DEVICE. PCS
+    /// stays set until the guest clears DIAG.X in PxSERR.
+    pub fn notify_device_changed(&mut self, port: usize) {
+        let Some(port) = self.ports.get_mut(port) else {
+            return;
+        };
+        if !port.present {
+            return;
+        }
+        port.regs.serr |= SERR_DIAG_EXCHANGED;
+        port.regs.sync_serr_status();
+        self.update_irq();
+    }
+
     /// Returns whether the given port currently has an attached [`AtaDrive`] backend.
     ///
     /// This is intended for platform integrations that need to re-attach host-side backends after
@@ -308,6 +522,8 @@ impl AhciController {
     pub fn reset(&mut self) {
         self.hba.reset();
         for port in &mut self.ports {
+            port.deferred.clear();
+            port.signature_fis_pending = false;
             port.regs = PortRegs::new(port.present);
             port.regs.update_running_bits();
         }
@@ -404,9 +620,8 @@ impl AhciController {
             return;
         }
 
-        // Preserve AE/IE and ignore reserved bits. AE is required for AHCI mode.
-        let masked = val & (GHC_IE | GHC_AE);
-        self.hba.ghc = masked;
+        // Only IE is writable: CAP.SAM makes AE read-only one, and other bits are reserved.
+        self.hba.ghc = (val & GHC_IE) | GHC_AE;
     }
 
     fn write_hba_is(&mut self, val: u32) {
@@ -438,15 +653,54 @@ impl AhciController {
                 port.regs.fb = (port.regs.fb & 0x0000_0000_FFFF_FFFF) | ((val as u64) << 32);
             }
             PORT_REG_IS => {
-                // Write 1 to clear.
-                port.regs.is &= !val;
+                // Write 1 to clear. PRCS/PCS are cleared via PxSERR.DIAG.N/X instead.
+                port.regs.is &= !(val & !(PORT_IS_PRCS | PORT_IS_PCS));
             }
             PORT_REG_IE => port.regs.ie = val,
             PORT_REG_CMD => {
-                // Preserve read-only bits (FR/CR) but otherwise allow the guest to program the
-                // full PxCMD register. Windows sets bits like SUD/POD in addition to ST/FRE.
-                port.regs.cmd = val & !(PORT_CMD_FR | PORT_CMD_CR);
+                let old = port.regs.cmd;
+                let was_running = port.regs.running();
+                let mut st = val & PORT_CMD_ST != 0;
+                let mut fre = val & PORT_CMD_FRE != 0;
+                // Interlocks (AHCI 1.3 §3.3.7): ST may only be set with FIS receive enabled, and
+                // FRE must not be cleared while the command list runs. A write violating them
+                // leaves the offending bit unchanged.
+                if st && !fre {
+                    if old & PORT_CMD_FRE != 0 {
+                        fre = true;
+                    } else {
+                        st = false;
+                    }
+                }
+
+                // Hardwired bits read as one; unsupported features (ICC, ALPE/ASP, PMA, ...) are
+                // read-only zero.
+                let mut cmd = PORT_CMD_HARDWIRED | (val & PORT_CMD_RW);
+                if st {
+                    cmd |= PORT_CMD_ST | (old & PORT_CMD_CCS_MASK);
+                }
+                if fre {
+                    cmd |= PORT_CMD_FRE;
+                }
+                port.regs.cmd = cmd;
                 port.regs.update_running_bits();
+
+                if !st {
+                    port.abort_deferred();
+                    if was_running {
+                        // Stopping the command list engine clears issued commands.
+                        port.regs.ci = 0;
+                        port.regs.sact = 0;
+                    }
+                    // Command List Override (CAP.SCLO): clear BSY/DRQ so a wedged device can be
+                    // recovered without a COMRESET. Only valid while stopped; self-clearing.
+                    if val & PORT_CMD_CLO != 0 {
+                        port.regs.tfd &= !u32::from(ATA_STATUS_BSY | ATA_STATUS_DRQ);
+                    }
+                }
+                if !fre {
+                    port.signature_fis_pending = false;
+                }
             }
             PORT_REG_SCTL => {
                 // Model the minimal PxSCTL.DET COMRESET sequence that many AHCI drivers use for
@@ -469,13 +723,20 @@ impl AhciController {
                 // COMRESET asserted: drop link status and mark the device busy.
                 if new_det == SCTL_DET_COMRESET {
                     // A link reset aborts any in-flight commands and clears transient status.
+                    port.deferred.clear();
+                    port.signature_fis_pending = false;
                     port.regs.ci = 0;
                     port.regs.sact = 0;
                     port.regs.is = 0;
 
-                    // Clear old SERR state, but latch a basic diagnostic bit so guests can observe
-                    // that a reset event occurred. PxSERR is W1C (handled in PORT_REG_SERR).
-                    port.regs.serr = SERR_DIAG_PHYRDY_CHANGE;
+                    // Clear old SERR state; an established link going down is a PhyRdy change
+                    // (DIAG.N, mirrored by PxIS.PRCS). PxSERR is W1C (handled in PORT_REG_SERR).
+                    port.regs.serr = if port.regs.phy_ready() {
+                        SERR_DIAG_PHYRDY_CHANGE
+                    } else {
+                        0
+                    };
+                    port.regs.sync_serr_status();
 
                     // Report a transient "device present but no communication" state if a drive is
                     // attached. This avoids guests interpreting the reset as a hot-unplug.
@@ -495,6 +756,8 @@ impl AhciController {
                 // DET=4 disables the port (PHY offline). Some guests use DET=2 for this too; treat
                 // it as an alias.
                 if matches!(new_det, SCTL_DET_DISABLE | SCTL_DET_DISABLE_ALT) {
+                    port.deferred.clear();
+                    port.signature_fis_pending = false;
                     port.regs.ci = 0;
                     port.regs.sact = 0;
                     port.regs.is = 0;
@@ -506,46 +769,27 @@ impl AhciController {
                 }
 
                 // COMRESET deasserted: if we were previously in DET=1, bring the link back up.
-                if old_det == SCTL_DET_COMRESET && new_det == 0 {
+                // COMRESET deasserted, or the guest re-enables a previously disabled port:
+                // complete link bring-up synchronously.
+                let link_was_held = old_det == SCTL_DET_COMRESET
+                    || matches!(old_det, SCTL_DET_DISABLE | SCTL_DET_DISABLE_ALT);
+                if link_was_held && new_det == 0 {
                     if port.present {
-                        // DET=3 (device present), SPD=1 (Gen1), IPM=1 (active).
-                        port.regs.ssts =
-                            SSTS_IPM_ACTIVE | SSTS_SPD_GEN1 | SSTS_DET_DEVICE_PRESENT_PHY;
-                        port.regs.sig = SATA_SIG_ATA;
-                        port.regs.tfd = u32::from(ATA_STATUS_DRDY | ATA_STATUS_DSC);
-
-                        // Signal that the device-to-host register FIS has been received.
-                        port.regs.is |= PORT_IS_DHRS;
+                        port.link_up();
                     } else {
-                        port.regs.ssts = SSTS_DET_NO_DEVICE;
-                        port.regs.sig = 0;
-                        port.regs.tfd = 0;
-                    }
-                }
-
-                // If the guest re-enables a previously disabled port, complete link bring-up
-                // synchronously (similar to COMRESET completion).
-                if matches!(old_det, SCTL_DET_DISABLE | SCTL_DET_DISABLE_ALT) && new_det == 0 {
-                    if port.present {
-                        port.regs.ssts =
-                            SSTS_IPM_ACTIVE | SSTS_SPD_GEN1 | SSTS_DET_DEVICE_PRESENT_PHY;
-                        port.regs.sig = SATA_SIG_ATA;
-                        port.regs.tfd = u32::from(ATA_STATUS_DRDY | ATA_STATUS_DSC);
-                        port.regs.is |= PORT_IS_DHRS;
-                    } else {
-                        port.regs.ssts = SSTS_DET_NO_DEVICE;
-                        port.regs.sig = 0;
-                        port.regs.tfd = 0;
+                        port.link_down_empty();
                     }
                 }
             }
             PORT_REG_SERR => {
                 // Write 1 to clear.
                 port.regs.serr &= !val;
+                port.regs.sync_serr_status();
             }
-            PORT_REG_SACT => port.regs.sact = val,
-            PORT_REG_CI => {
-                // Writing to CI sets command issue bits.
+            // PxSACT/PxCI bits are set by software (writing zero has no effect) and only while
+            // the command list engine runs; stopping it clears them.
+            PORT_REG_SACT if port.regs.running() => port.regs.sact |= val,
+            PORT_REG_CI if port.regs.running() => {
                 port.regs.ci |= val;
 
                 // If the port is operational, reflect that work is pending via PxTFD.BSY.
@@ -575,10 +819,31 @@ impl AhciController {
     }
 
     fn process_port(&mut self, port_idx: usize, mem: &mut dyn MemoryBus) {
+        let now_ns = self.now_ns;
         let Some(port) = self.ports.get_mut(port_idx) else {
             return;
         };
 
+        // Complete deferred commands that are due (all of them once the injector is removed).
+        if !port.deferred.is_empty() {
+            let all = self.fault_injector.is_none();
+            let (mut due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut port.deferred)
+                .into_iter()
+                .partition(|cmd| all || cmd.due_ns <= now_ns);
+            port.deferred = pending;
+            due.sort_by_key(|cmd| cmd.due_ns);
+            for cmd in due {
+                finish_command(&mut port.regs, cmd.slot, cmd.result, mem);
+            }
+            if !port.deferred.is_empty() {
+                port.regs.tfd = u32::from(ATA_STATUS_BSY);
+            }
+        }
+
+        if std::mem::take(&mut port.signature_fis_pending) {
+            write_signature_fis(mem, port.regs.fb, port.regs.sig, port.regs.tfd as u8);
+        }
+
         // If COMRESET is asserted (PxSCTL.DET=1), commands must not execute.
         if port.regs.sctl_det() == SCTL_DET_COMRESET {
             return;
@@ -587,9 +852,6 @@ impl AhciController {
         if (port.regs.ssts & SSTS_DET_MASK) != SSTS_DET_DEVICE_PRESENT_PHY {
             return;
         }
-        let Some(drive) = port.drive.as_mut() else {
-            return;
-        };
         if !port.regs.running() || !port.regs.fis_receive_enabled() {
             return;
         }
@@ -597,30 +859,79 @@ impl AhciController {
             return;
         }
 
-        while port.regs.ci != 0 {
-            let slot = port.regs.ci.trailing_zeros() as usize;
-            let bit = 1u32 << slot;
-            if slot >= 32 {
-                port.regs.ci &= !bit;
-                continue;
-            }
+        // Slots whose completion is deferred stay set in PxCI but must not execute again.
+        let mut issued = port.regs.ci & !port.deferred_slots();
+        let Some(drive) = port.drive.as_mut() else {
+            return;
+        };
+        // FLUSH CACHE commands issued in the same pass share one backend flush: their completion
+        // is held until the end of the pass, and writes issued after them are ordered behind them
+        // with a write barrier instead.
+        let mut held_flushes: Vec<(usize, u64)> = Vec::new();
+        let mut barrier_needed = false;
+        while issued != 0 {
+            let slot = issued.trailing_zeros() as usize;
+            issued &= !(1u32 << slot);
 
             // Mark the task file as busy while we process the command.
             port.regs.tfd = u32::from(ATA_STATUS_BSY);
-
-            match process_command_slot(drive, &mut port.regs, slot, mem) {
-                Ok(()) => {}
-                Err(_) => {
-                    // Report an aborted command via task file status/error.
-                    let status = ATA_STATUS_DRDY | ATA_STATUS_DSC | ATA_STATUS_ERR;
-                    port.regs.tfd = (status as u32) | ((ATA_ERROR_ABRT as u32) << 8);
-                    port.regs.serr |= SERR_ERR_PROTOCOL;
-                    write_d2h_fis(mem, port.regs.fb, status, ATA_ERROR_ABRT);
-                    port.regs.is |= PORT_IS_DHRS | PORT_IS_TFES;
+            port.regs.cmd =
+                (port.regs.cmd & !PORT_CMD_CCS_MASK) | ((slot as u32) << PORT_CMD_CCS_SHIFT);
+
+            let request = storage_request_for_slot(&port.regs, slot, mem);
+            let kind = request.map(|req| req.kind);
+            let fault = self
+                .fault_injector
+                .as_mut()
+                .zip(request)
+                .map(|(injector, req)| injector.on_request(req));
+            let delay_ns = fault.map_or(0, |fault| fault.delay_ns);
+            let result = match fault.and_then(|fault| fault.error) {
+                Some(err) => Err(io::Error::other(err.to_disk_error())),
+                None if kind == Some(StorageRequestKind::Flush) => {
+                    held_flushes.push((slot, delay_ns));
+                    barrier_needed = true;
+                    continue;
                 }
-            }
+                None => {
+                    let barrier = if barrier_needed && kind == Some(StorageRequestKind::Write) {
+                        barrier_needed = false;
+                        drive.write_barrier()
+                    } else {
+                        Ok(())
+                    };
+                    barrier.and_then(|()| process_command_slot(drive, &port.regs, slot, mem))
+                }
+            };
+            finish_or_defer(
+                &mut port.regs,
+                &mut port.deferred,
+                slot,
+                result,
+                now_ns,
+                delay_ns,
+                mem,
+            );
+        }
 
-            port.regs.ci &= !bit;
+        if held_flushes.is_empty() {
+            return;
+        }
+        let flushed = drive.flush();
+        for (slot, delay_ns) in held_flushes {
+            let result = match &flushed {
+                Ok(()) => Ok(0),
+                Err(err) => Err(clone_io_error(err)),
+            };
+            finish_or_defer(
+                &mut port.regs,
+                &mut port.deferred,
+                slot,
+                result,
+                now_ns,
+                delay_ns,
+                mem,
+            );
         }
     }
 }
@@ -677,6 +988,7 @@ impl AhciController {
         for port in &mut self.ports {
             port.drive = None;
             port.present = false;
+            port.deferred.clear();
             port.regs = PortRegs::new(false);
             port.regs.update_running_bits();
         }
@@ -689,7 +1001,7 @@ impl AhciController {
             port.regs.fb = p.fb;
             port.regs.is = p.is;
             port.regs.ie = p.ie;
-            port.regs.cmd = p.cmd;
+            port.regs.cmd = p.cmd | PORT_CMD_HARDWIRED;
             port.regs.tfd = p.tfd;
             port.regs.sig = p.sig;
             port.regs.ssts = p.ssts;
@@ -712,7 +1024,8 @@ impl IoSnapshot for AhciController {
     fn save_state(&self) -> Vec<u8> {
         // This AHCI model completes commands synchronously in [`AhciController::process`]. Any
         // outstanding work is fully represented by guest-visible registers (e.g. PxCI/PxSACT), so
-        // we don't need additional in-flight bookkeeping in the snapshot.
+        // we don't need additional in-flight bookkeeping in the snapshot. Commands deferred by a
+        // fault injector keep their PxCI bit set, so they simply execute again after restore.
         self.snapshot_state().save_state()
     }
 
@@ -730,12 +1043,15 @@ fn decode_port_offset(offset: u64) -> (usize, u64) {
     (port_idx, reg_off)
 }
 
+/// Execute the command in `slot`, returning the number of bytes transferred.
+///
+/// Completion (PRDBC, D2H FIS, PxCI) is reported separately by [`finish_command`].
 fn process_command_slot(
     drive: &mut AtaDrive,
-    port_regs: &mut PortRegs,
+    port_regs: &PortRegs,
     slot: usize,
     mem: &mut dyn MemoryBus,
-) -> io::Result<()> {
+) -> io::Result<u32> {
     // Guest-controlled DMA base addresses can be arbitrary; use wrapping arithmetic so malformed
     // values cannot trigger an overflow panic when fuzzing/debug overflow checks are enabled.
     let header_addr = port_regs.clb.wrapping_add((slot as u64).wrapping_mul(32));
@@ -754,11 +1070,11 @@ fn process_command_slot(
     }
 
     let command = cfis[2];
-    match command {
+    let bytes_transferred = match command {
         ATA_CMD_IDENTIFY => {
             let identify = drive.identify_sector();
             dma_write_from_host_buffer(mem, &header, identify)?;
-            complete_command(mem, port_regs, slot, (identify.len()) as u32);
+            identify.len() as u32
         }
         ATA_CMD_READ_DMA | ATA_CMD_READ_SECTORS => {
             // Even for PIO opcodes, AHCI uses PRDT scatter/gather DMA.
@@ -766,7 +1082,7 @@ fn process_command_slot(
             let sector_count = extract_sector_count_28(&cfis);
             let byte_len = sector_count as usize * SECTOR_SIZE;
             dma_read_sectors_into_guest(mem, &header, drive, lba, byte_len)?;
-            complete_command(mem, port_regs, slot, byte_len as u32);
+            byte_len as u32
         }
         ATA_CMD_READ_DMA_EXT | ATA_CMD_READ_SECTORS_EXT => {
             // Even for PIO opcodes, AHCI uses PRDT scatter/gather DMA.
@@ -774,7 +1090,7 @@ fn process_command_slot(
             let sector_count = extract_sector_count(&cfis);
             let byte_len = sector_count as usize * SECTOR_SIZE;
             dma_read_sectors_into_guest(mem, &header, drive, lba, byte_len)?;
-            complete_command(mem, port_regs, slot, byte_len as u32);
+            byte_len as u32
         }
         ATA_CMD_WRITE_DMA | ATA_CMD_WRITE_SECTORS => {
             // Even for PIO opcodes, AHCI uses PRDT scatter/gather DMA.
@@ -782,7 +1098,7 @@ fn process_command_slot(
             let sector_count = extract_sector_count_28(&cfis);
             let byte_len = sector_count as usize * SECTOR_SIZE;
             dma_write_sectors_from_guest(mem, &header, drive, lba, byte_len)?;
-            complete_command(mem, port_regs, slot, byte_len as u32);
+            byte_len as u32
         }
         ATA_CMD_WRITE_DMA_EXT | ATA_CMD_WRITE_SECTORS_EXT => {
             // Even for PIO opcodes, AHCI uses PRDT scatter/gather DMA.
@@ -790,11 +1106,11 @@ fn process_command_slot(
             let sector_count = extract_sector_count(&cfis);
             let byte_len = sector_count as usize * SECTOR_SIZE;
             dma_write_sectors_from_guest(mem, &header, drive, lba, byte_len)?;
-            complete_command(mem, port_regs, slot, byte_len as u32);
+            byte_len as u32
         }
         ATA_CMD_FLUSH_CACHE | ATA_CMD_FLUSH_CACHE_EXT => {
             drive.flush()?;
-            complete_command(mem, port_regs, slot, 0);
+            0
         }
         ATA_CMD_SET_FEATURES => {
             // Subcommand is in Features (low byte).
@@ -803,7 +1119,7 @@ fn process_command_slot(
                 0x82 => drive.set_write_cache_enabled(false),
                 _ => {}
             }
-            complete_command(mem, port_regs, slot, 0);
+            0
         }
         _ => {
             return Err(io::Error::new(
@@ -811,9 +1127,9 @@ fn process_command_slot(
                 format!("unsupported ATA command 0x{command:02x}"),
             ));
         }
-    }
+    };
 
-    Ok(())
+    Ok(bytes_transferred)
 }
 
 fn extract_lba28(cfis: &[u8; 64]) -> io::Result<u64> {
@@ -1105,6 +1421,96 @@ fn dma_write_from_host_buffer(
     Ok(())
 }
 
+/// Complete the command in `slot`, or hold it back for an injected delay of `delay_ns`.
+fn finish_or_defer(
+    port_regs: &mut PortRegs,
+    deferred: &mut Vec<DeferredCommand>,
+    slot: usize,
+    result: io::Result<u32>,
+    now_ns: u64,
+    delay_ns: u64,
+    mem: &mut dyn MemoryBus,
+) {
+    match delay_ns {
+        0 => finish_command(port_regs, slot, result, mem),
+        delay_ns => deferred.push(DeferredCommand {
+            due_ns: now_ns.saturating_add(delay_ns),
+            slot,
+            result,
+        }),
+    }
+}
+
+/// Report the outcome of the command in `slot` to the guest and clear its PxCI bit.
+fn finish_command(
+    port_regs: &mut PortRegs,
+    slot: usize,
+    result: io::Result<u32>,
+    mem: &mut dyn MemoryBus,
+) {
+    match result {
+        Ok(bytes_transferred) => complete_command(mem, port_regs, slot, bytes_transferred),
+        Err(err) => {
+            // Report the failure via task file status/error: an aborted command, or a retryable
+            // media error when the backend is temporarily unavailable.
+            let error = ata_error_for_io_error(&err);
+            let status = ATA_STATUS_DRDY | ATA_STATUS_DSC | ATA_STATUS_ERR;
+            port_regs.tfd = (status as u32) | ((error as u32) << 8);
+            if error == ATA_ERROR_ABRT {
+                port_regs.serr |= SERR_ERR_PROTOCOL;
+            }
+            write_d2h_fis(mem, port_regs.fb, status, error);
+            port_regs.is |= PORT_IS_DHRS | PORT_IS_TFES;
+        }
+    }
+    port_regs.ci &= !(1u32 << slot);
+}
+
+/// Decode the disk request issued by the command in `slot`, if it accesses the disk.
+fn storage_request_for_slot(
+    port_regs: &PortRegs,
+    slot: usize,
+    mem: &mut dyn MemoryBus,
+) -> Option<StorageRequest> {
+    let header_addr = port_regs.clb.wrapping_add((slot as u64).wrapping_mul(32));
+    let header = CommandHeader::read(mem, header_addr);
+    let mut cfis = [0u8; 64];
+    mem.read_physical(header.ctba, &mut cfis);
+    if cfis[0] != 0x27 {
+        return None;
+    }
+
+    let (kind, lba, sectors) = match cfis[2] {
+        ATA_CMD_READ_DMA | ATA_CMD_READ_SECTORS => (
+            StorageRequestKind::Read,
+            extract_lba28(&cfis).ok()?,
+            extract_sector_count_28(&cfis),
+        ),
+        ATA_CMD_READ_DMA_EXT | ATA_CMD_READ_SECTORS_EXT => (
+            StorageRequestKind::Read,
+            extract_lba48(&cfis),
+            extract_sector_count(&cfis),
+        ),
+        ATA_CMD_WRITE_DMA | ATA_CMD_WRITE_SECTORS => (
+            StorageRequestKind::Write,
+            extract_lba28(&cfis).ok()?,
+            extract_sector_count_28(&cfis),
+        ),
+        ATA_CMD_WRITE_DMA_EXT | ATA_CMD_WRITE_SECTORS_EXT => (
+            StorageRequestKind::Write,
+            extract_lba48(&cfis),
+            extract_sector_count(&cfis),
+        ),
+        ATA_CMD_FLUSH_CACHE | ATA_CMD_FLUSH_CACHE_EXT => (StorageRequestKind::Flush, 0, 0),
+        _ => return None,
+    };
+    Some(StorageRequest {
+        kind,
+        lba,
+        sectors: u64::from(sectors),
+    })
+}
+
 fn complete_command(
     mem: &mut dyn MemoryBus,
     port_regs: &mut PortRegs,
@@ -1124,6 +1530,22 @@ fn complete_command(
     port_regs.is |= PORT_IS_DHRS;
 }
 
+/// Post the D2H Register FIS a device sends after link-up, carrying its signature (PxSIG layout:
+/// LBA high/mid/low, sector count) in the LBA and count fields.
+fn write_signature_fis(mem: &mut dyn MemoryBus, fb: u64, sig: u32, status: u8) {
+    let [count, lba_low, lba_mid, lba_high] = sig.to_le_bytes();
+    let mut fis = [0u8; 20];
+    fis[0] = 0x34; // FIS_TYPE_REG_D2H
+    fis[1] = 1 << 6; // I bit (interrupt)
+    fis[2] = status;
+    fis[3] = 0x01; // Diagnostic code: no error detected
+    fis[4] = lba_low;
+    fis[5] = lba_mid;
+    fis[6] = lba_high;
+    fis[12] = count;
+    mem.write_physical(fb.wrapping_add(0x40), &fis);
+}
+
 fn write_d2h_fis(mem: &mut dyn MemoryBus, fb: u64, status: u8, error: u8) {
     // Received FIS layout places the D2H Register FIS at offset 0x40.
     let mut fis = [0u8; 20];
@@ -1441,7 +1863,8 @@ mod tests {
 
         assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_CI), 0);
         assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_SACT), 0);
-        assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_IS), 0);
+        // The link going down is reported via DIAG.N and its PxIS.PRCS mirror only.
+        assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_IS), PORT_IS_PRCS);
         assert_eq!(
             ctl.read_u32(PORT_BASE + PORT_REG_SERR),
             SERR_DIAG_PHYRDY_CHANGE
@@ -1619,6 +2042,7 @@ mod tests {
         let cfg = AeroSparseConfig {
             disk_size_bytes: (high_lba + 16) * SECTOR_SIZE as u64,
             block_size_bytes: 1024 * 1024,
+            ..Default::default()
         };
         let mut disk = AeroSparseDisk::create(MemBackend::new(), cfg).unwrap();
 
@@ -1750,6 +2174,7 @@ mod tests {
         let cfg = AeroSparseConfig {
             disk_size_bytes: (high_lba + 16) * SECTOR_SIZE as u64,
             block_size_bytes: 1024 * 1024,
+            ..Default::default()
         };
         let mut disk = AeroSparseDisk::create(MemBackend::new(), cfg).unwrap();
 
@@ -1906,6 +2331,7 @@ mod tests {
             AeroSparseConfig {
                 disk_size_bytes: capacity_bytes,
                 block_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -2512,4 +2938,29 @@ mod tests {
         ctl.write_u32(PORT_BASE + PORT_REG_SERR, 0);
         assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_SERR), 0xA5A5_0505);
     }
+
+    #[test]
+    fn device_change_latches_pcs_until_serr_diag_x_is_cleared() {
+        let (mut ctl, irq, _mem, drive) = setup_controller();
+        ctl.attach_drive(0, drive);
+        ctl.write_u32(HBA_REG_GHC, GHC_IE | GHC_AE);
+        ctl.write_u32(PORT_BASE + PORT_REG_IE, PORT_IS_PCS);
+
+        ctl.notify_device_changed(0);
+        assert_ne!(ctl.read_u32(PORT_BASE + PORT_REG_IS) & PORT_IS_PCS, 0);
+        assert_ne!(
+            ctl.read_u32(PORT_BASE + PORT_REG_SERR) & SERR_DIAG_EXCHANGED,
+            0
+        );
+        assert!(irq.level());
+
+        // PCS is not directly W1C.
+        ctl.write_u32(PORT_BASE + PORT_REG_IS, PORT_IS_PCS);
+        assert_ne!(ctl.read_u32(PORT_BASE + PORT_REG_IS) & PORT_IS_PCS, 0);
+        assert!(irq.level());
+
+        ctl.write_u32(PORT_BASE + PORT_REG_SERR, SERR_DIAG_EXCHANGED);
+        assert_eq!(ctl.read_u32(PORT_BASE + PORT_REG_IS) & PORT_IS_PCS, 0);
+        assert!(!irq.level());
+    }
 }
@@ -14,6 +14,7 @@ pub const ATA_STATUS_DRQ: u8 = 0x08;
 pub const ATA_STATUS_ERR: u8 = 0x01;
 
 pub const ATA_ERROR_ABRT: u8 = 0x04;
+pub const ATA_ERROR_UNC: u8 = 0x40;
 
 pub const ATA_CMD_IDENTIFY: u8 = 0xEC;
 pub const ATA_CMD_IDENTIFY_PACKET: u8 = 0xA1;
@@ -183,6 +184,11 @@ impl AtaDrive {
         self.disk.flush().map_err(map_disk_error)
     }
 
+    /// Order writes issued so far ahead of later ones (see [`VirtualDisk::write_barrier`]).
+    pub fn write_barrier(&mut self) -> io::Result<()> {
+        self.disk.write_barrier().map_err(map_disk_error)
+    }
+
     pub fn set_write_cache_enabled(&mut self, enabled: bool) {
         self.write_cache_enabled = enabled;
         self.sync_identify_write_cache_enabled();
@@ -337,8 +343,9 @@ fn build_identify_sector(sector_count: u64) -> [u8; SECTOR_SIZE] {
     // Word 0: general configuration: non-removable, hard disk.
     words[0] = 0x0040;
 
-    // Provide plausible geometry (mostly ignored when LBA is supported).
-    words[1] = 16383; // cylinders
+    // Default geometry (mostly ignored when LBA is supported). The BIOS reports the same
+    // geometry in INT 13h AH=48h, so keep the two in sync.
+    words[1] = (sector_count / (16 * 63)).clamp(1, 16383) as u16; // cylinders
     words[3] = 16; // heads
     words[6] = 63; // sectors/track
 
@@ -410,6 +417,36 @@ fn map_disk_error(err: DiskError) -> io::Error {
     io::Error::other(err)
 }
 
+/// Duplicate a [`map_disk_error`]-style error for reporting it on several commands.
+pub(crate) fn clone_io_error(err: &io::Error) -> io::Error {
+    match err
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<DiskError>())
+    {
+        Some(disk_err) => io::Error::other(disk_err.clone()),
+        None => io::Error::new(err.kind(), err.to_string()),
+    }
+}
+
+/// ATA error register value to report for a failed [`AtaDrive`] transfer.
+///
+/// A backend that is temporarily unavailable (e.g. a streaming disk that went offline) is
+/// reported as an uncorrectable data error, which guest disk drivers retry; anything else aborts
+/// the command.
+pub fn ata_error_for_io_error(err: &io::Error) -> u8 {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<DiskError>())
+        .map_or(ATA_ERROR_ABRT, ata_error_for_disk_error)
+}
+
+/// Like [`ata_error_for_io_error`], for a disk-layer error.
+pub fn ata_error_for_disk_error(err: &DiskError) -> u8 {
+    match err.root() {
+        DiskError::BackendUnavailable => ATA_ERROR_UNC,
+        _ => ATA_ERROR_ABRT,
+    }
+}
+
 fn write_ata_string(words: &mut [u16; 256], start: usize, len_words: usize, s: &str) {
     let mut bytes = vec![b' '; len_words * 2];
     let s_bytes = s.as_bytes();
@@ -517,4 +554,37 @@ mod tests {
             "IDENTIFY data should return to its original value after toggling back"
         );
     }
+    #[test]
+    fn unavailable_backend_maps_to_retryable_uncorrectable_error() {
+        struct UnavailableDisk(fn() -> DiskError);
+
+        impl VirtualDisk for UnavailableDisk {
+            fn capacity_bytes(&self) -> u64 {
+                16 * SECTOR_SIZE as u64
+            }
+
+            fn read_at(&mut self, _offset: u64, _buf: &mut [u8]) -> aero_storage::Result<()> {
+                Err((self.0)())
+            }
+
+            fn write_at(&mut self, _offset: u64, _buf: &[u8]) -> aero_storage::Result<()> {
+                Err((self.0)())
+            }
+
+            fn flush(&mut self) -> aero_storage::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut buf = [0u8; SECTOR_SIZE];
+        let mut drive =
+            AtaDrive::new(Box::new(UnavailableDisk(|| DiskError::BackendUnavailable))).unwrap();
+        let err = drive.read_sectors(0, &mut buf).unwrap_err();
+        assert_eq!(ata_error_for_io_error(&err), ATA_ERROR_UNC);
+
+        let mut drive =
+            AtaDrive::new(Box::new(UnavailableDisk(|| DiskError::Io("broken".into())))).unwrap();
+        let err = drive.read_sectors(0, &mut buf).unwrap_err();
+        assert_eq!(ata_error_for_io_error(&err), ATA_ERROR_ABRT);
+    }
 }
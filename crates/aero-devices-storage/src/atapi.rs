@@ -12,7 +12,10 @@ pub type IsoDisk = Box<dyn VirtualDisk>;
 
 /// Read-only ISO9660 (or raw CD) backing store.
 ///
-/// The IDE/ATAPI layer treats the image as a sequence of 2048-byte sectors.
+/// The IDE/ATAPI layer treats the image as a sequence of 2048-byte sectors. Sector counts and LBAs
+/// are 64-bit so images larger than 4 GiB (e.g. Windows 7 SP1 media with integrated updates) are
+/// addressed without truncation; the guest-visible 32-bit CDB LBA fields are widened, never the
+/// other way around.
 ///
 /// # Canonical trait note
 ///
@@ -26,8 +29,8 @@ pub type IsoDisk = Box<dyn VirtualDisk>;
 /// See `docs/20-storage-trait-consolidation.md`.
 #[cfg(not(target_arch = "wasm32"))]
 pub trait IsoBackend: Send {
-    fn sector_count(&self) -> u32;
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()>;
+    fn sector_count(&self) -> u64;
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()>;
 }
 
 /// wasm32 variant of [`IsoBackend`].
@@ -36,8 +39,8 @@ pub trait IsoBackend: Send {
 /// cross threads, so we avoid imposing a `Send` bound on ISO backends in wasm builds.
 #[cfg(target_arch = "wasm32")]
 pub trait IsoBackend {
-    fn sector_count(&self) -> u32;
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()>;
+    fn sector_count(&self) -> u64;
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()>;
 }
 
 // Compile-time guard that we can implement ISO backends using !Send JS/DOM handles in wasm builds
@@ -55,11 +58,11 @@ mod wasm_send_bounds_check {
     struct NotSendIsoBackend(Rc<()>);
 
     impl IsoBackend for NotSendIsoBackend {
-        fn sector_count(&self) -> u32 {
+        fn sector_count(&self) -> u64 {
             0
         }
 
-        fn read_sectors(&mut self, _lba: u32, buf: &mut [u8]) -> io::Result<()> {
+        fn read_sectors(&mut self, _lba: u64, buf: &mut [u8]) -> io::Result<()> {
             buf.fill(0);
             Ok(())
         }
@@ -77,7 +80,7 @@ mod wasm_send_bounds_check {
 /// storage backend) as an ATAPI CD-ROM.
 pub struct VirtualDiskIsoBackend {
     disk: IsoDisk,
-    sector_count: u32,
+    sector_count: u64,
 }
 
 impl VirtualDiskIsoBackend {
@@ -91,23 +94,17 @@ impl VirtualDiskIsoBackend {
         }
 
         let sector_count = capacity / AtapiCdrom::SECTOR_SIZE as u64;
-        let sector_count = u32::try_from(sector_count).map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "ISO disk capacity exceeds 32-bit sector count limit",
-            )
-        })?;
 
         Ok(Self { disk, sector_count })
     }
 }
 
 impl IsoBackend for VirtualDiskIsoBackend {
-    fn sector_count(&self) -> u32 {
+    fn sector_count(&self) -> u64 {
         self.sector_count
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         if !buf.len().is_multiple_of(AtapiCdrom::SECTOR_SIZE) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -115,7 +112,7 @@ impl IsoBackend for VirtualDiskIsoBackend {
             ));
         }
 
-        let offset = u64::from(lba)
+        let offset = lba
             .checked_mul(AtapiCdrom::SECTOR_SIZE as u64)
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "offset overflow"))?;
 
@@ -511,12 +508,18 @@ impl AtapiCdrom {
         data
     }
 
+    /// READ CAPACITY(10) payload.
+    ///
+    /// The capacity always comes from the backend size. It is never taken from the ISO9660 volume
+    /// space size: UDF-bridge discs keep most of their data outside the ISO9660 volume, so the
+    /// primary volume descriptor undercounts them. Media with more than 2^32 sectors report
+    /// `0xFFFF_FFFF` as SBC requires.
     fn read_capacity_10(&mut self) -> io::Result<Vec<u8>> {
         let Some(backend) = self.backend.as_ref() else {
             return Err(io::Error::new(io::ErrorKind::NotFound, "no media"));
         };
         let sectors = backend.sector_count();
-        let last_lba = sectors.saturating_sub(1);
+        let last_lba = u32::try_from(sectors.saturating_sub(1)).unwrap_or(u32::MAX);
         let mut out = vec![0u8; 8];
         out[..4].copy_from_slice(&last_lba.to_be_bytes());
         out[4..].copy_from_slice(&(Self::SECTOR_SIZE as u32).to_be_bytes());
@@ -528,6 +531,16 @@ impl AtapiCdrom {
             self.set_sense(SENSE_NO_SENSE, 0, 0);
             return PacketResult::NoDataSuccess;
         }
+        let sectors = self.backend.as_ref().map(|b| b.sector_count()).unwrap_or(0);
+        if u64::from(lba) + u64::from(blocks) > sectors {
+            // LOGICAL BLOCK ADDRESS OUT OF RANGE.
+            self.set_sense(SENSE_ILLEGAL_REQUEST, 0x21, 0);
+            return PacketResult::Error {
+                sense_key: SENSE_ILLEGAL_REQUEST,
+                asc: 0x21,
+                ascq: 0,
+            };
+        }
         let len = match blocks
             .checked_mul(Self::SECTOR_SIZE as u32)
             .and_then(|v| usize::try_from(v).ok())
@@ -564,7 +577,7 @@ impl AtapiCdrom {
             }
         };
         let res = if let Some(backend) = self.backend.as_mut() {
-            backend.read_sectors(lba, &mut buf)
+            backend.read_sectors(u64::from(lba), &mut buf)
         } else {
             Err(io::Error::new(io::ErrorKind::NotFound, "no media"))
         };
@@ -590,7 +603,7 @@ impl AtapiCdrom {
 
     fn read_toc(&mut self) -> Vec<u8> {
         let sectors = self.backend.as_ref().map(|b| b.sector_count()).unwrap_or(0);
-        let lead_out_lba = sectors;
+        let lead_out_lba = u32::try_from(sectors).unwrap_or(u32::MAX);
 
         // Header (4 bytes) + 2 descriptors (track 1 + lead-out) = 4 + 16 = 20 bytes.
         let mut out = vec![0u8; 20];
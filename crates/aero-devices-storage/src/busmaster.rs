@@ -2,6 +2,9 @@ use memory::MemoryBus;
 
 use aero_io_snapshot::io::storage::state::IdeBusMasterChannelState;
 
+/// End of the 32-bit physical address space reachable by the Bus Master IDE engine.
+const FOUR_GIB: u64 = 1 << 32;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DmaDirection {
     /// Device -> guest memory.
@@ -16,12 +19,24 @@ pub enum DmaError {
     DirectionMismatch,
     /// The PRD table ended before transferring the entire request buffer.
     PrdTooShort,
-    /// The PRD table did not terminate before transferring the full buffer (missing EOT).
+    /// The transfer ended exactly at the end of a PRD entry without EOT, or the table exceeded
+    /// [`BusMasterChannel`]'s per-transfer entry cap.
     PrdMissingEndOfTable,
 }
 
 pub type DmaResult<T> = Result<T, DmaError>;
 
+/// How a successful transfer left the PRD table, which decides the BMIDE status on completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrdCompletion {
+    /// The transfer ended exactly at the end of the EOT entry: ACTIVE clears, INTERRUPT sets.
+    TableExhausted,
+    /// The command's byte count ran out before the table did (the PRDs describe more than the
+    /// command transfers). Per the PIIX spec ACTIVE stays set alongside INTERRUPT until the driver
+    /// stops the engine.
+    TableRemaining,
+}
+
 #[derive(Debug)]
 pub enum DmaCommit {
     AtaWrite { lba: u64, sectors: u64 },
@@ -200,7 +215,18 @@ impl BusMasterChannel {
         }
     }
 
-    pub fn execute_dma(&mut self, mem: &mut dyn MemoryBus, req: &mut DmaRequest) -> DmaResult<()> {
+    /// Walk the PRD table and move `req.buffer` to or from guest memory.
+    ///
+    /// The transfer stops exactly at the end of the request buffer (the command's byte count),
+    /// even in the middle of a PRD entry, and no further descriptors are read. An entry that
+    /// extends past the command's data is a larger-than-transfer table
+    /// ([`PrdCompletion::TableRemaining`]); one that ends exactly with the data must carry EOT.
+    /// ACTIVE is set here and left for `finish_*` to update.
+    pub fn execute_dma(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        req: &mut DmaRequest,
+    ) -> DmaResult<PrdCompletion> {
         let bm_dir = if (self.cmd & 0x08) != 0 {
             DmaDirection::ToMemory
         } else {
@@ -214,64 +240,65 @@ impl BusMasterChannel {
 
         let mut remaining = req.buffer.len();
         let mut buf_off = 0usize;
-        let mut prd_ptr = self.prd_addr as u64;
+        // PIIX bus mastering is a 32-bit engine: both the PRD pointer and the buffer addresses wrap
+        // at 4GiB rather than reaching RAM the chipset remaps above it.
+        let mut prd_ptr = self.prd_addr;
 
-        // Guard against a guest that provides a PRD list with no EOT bit set and uses
-        // byte_count=0 (64KiB) entries forever. The transfer will still complete once
-        // `remaining` hits 0, but we want to guarantee termination even if the guest provides
-        // pathological PRDs that don't make progress (e.g. zero-length buffer).
+        // A command without data leaves the whole table untouched.
         if remaining == 0 {
-            return Ok(());
+            return Ok(PrdCompletion::TableRemaining);
         }
 
-        let mut saw_eot = false;
-        let mut entries_processed = 0usize;
-        while remaining > 0 {
-            if entries_processed >= Self::MAX_PRD_ENTRIES_PER_DMA {
-                // We ran out of PRD entries we're willing to process; treat as a malformed/hostile
-                // PRD list (missing EOT / too fragmented).
-                return Err(DmaError::PrdMissingEndOfTable);
-            }
-            entries_processed += 1;
-
-            let prd = PrdEntry::read_from(mem, prd_ptr);
+        // The entry cap guarantees termination even for a guest that never sets EOT and uses
+        // pathologically small entries.
+        for _ in 0..Self::MAX_PRD_ENTRIES_PER_DMA {
+            let prd = PrdEntry::read_from(mem, u64::from(prd_ptr));
             prd_ptr = prd_ptr.wrapping_add(8);
 
             let seg_len = prd.effective_len().min(remaining);
-            let addr = prd.addr as u64;
-
-            match req.direction {
-                DmaDirection::ToMemory => {
-                    mem.write_physical(addr, &req.buffer[buf_off..buf_off + seg_len]);
+            // A segment that runs past 4GiB continues at physical address 0.
+            let below_4g = (seg_len as u64).min(FOUR_GIB - u64::from(prd.addr)) as usize;
+            for (addr, range) in [
+                (u64::from(prd.addr), buf_off..buf_off + below_4g),
+                (0, buf_off + below_4g..buf_off + seg_len),
+            ] {
+                if range.is_empty() {
+                    continue;
                 }
-                DmaDirection::FromMemory => {
-                    mem.read_physical(addr, &mut req.buffer[buf_off..buf_off + seg_len]);
+                debug_assert!(addr + range.len() as u64 <= FOUR_GIB);
+                match req.direction {
+                    DmaDirection::ToMemory => mem.write_physical(addr, &req.buffer[range]),
+                    DmaDirection::FromMemory => mem.read_physical(addr, &mut req.buffer[range]),
                 }
             }
 
             buf_off += seg_len;
             remaining -= seg_len;
 
-            if prd.end_of_table {
-                saw_eot = true;
-                if remaining != 0 {
-                    return Err(DmaError::PrdTooShort);
-                }
-                break;
+            if seg_len < prd.effective_len() {
+                // The command's data ran out inside this entry; the table describes more.
+                return Ok(PrdCompletion::TableRemaining);
+            }
+            match (prd.end_of_table, remaining) {
+                (true, 0) => return Ok(PrdCompletion::TableExhausted),
+                // The table was exhausted before the command's byte count.
+                (true, _) => return Err(DmaError::PrdTooShort),
+                // The data ended exactly with an unterminated entry. Real hardware behavior
+                // varies, but Windows drivers expect an EOT.
+                (false, 0) => return Err(DmaError::PrdMissingEndOfTable),
+                (false, _) => {}
             }
         }
 
-        // If the DMA transfer completed without encountering an EOT PRD, treat this as a PRD
-        // table error. Real hardware behavior varies, but Windows drivers expect an EOT.
-        if !saw_eot {
-            return Err(DmaError::PrdMissingEndOfTable);
-        }
-
-        Ok(())
+        // We ran out of PRD entries we're willing to process; treat as a malformed/hostile PRD
+        // list (missing EOT / too fragmented).
+        Err(DmaError::PrdMissingEndOfTable)
     }
 
-    pub fn finish_success(&mut self) {
-        self.status &= !0x01; // active
+    pub fn finish_success(&mut self, completion: PrdCompletion) {
+        if completion == PrdCompletion::TableExhausted {
+            self.status &= !0x01; // active
+        }
         self.status &= !0x02; // error
         self.status |= 0x04; // interrupt
     }
@@ -494,4 +521,53 @@ mod tests {
             "finish_error should set IRQ+ERR and clear ACTIVE"
         );
     }
+
+    /// Byte-addressed memory covering the full 64-bit space, so accesses that escape the 32-bit
+    /// window are observable instead of being folded back into a small backing buffer.
+    #[derive(Default)]
+    struct SparseBytes(std::collections::BTreeMap<u64, u8>);
+
+    impl MemoryBus for SparseBytes {
+        fn read_physical(&mut self, paddr: u64, buf: &mut [u8]) {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = self.0.get(&(paddr + i as u64)).copied().unwrap_or(0);
+            }
+        }
+
+        fn write_physical(&mut self, paddr: u64, buf: &[u8]) {
+            for (i, b) in buf.iter().enumerate() {
+                self.0.insert(paddr + i as u64, *b);
+            }
+        }
+    }
+
+    #[test]
+    fn prd_pointer_and_buffers_wrap_at_4gib() {
+        let mut mem = SparseBytes::default();
+
+        // Entry 0 sits in the last 8 bytes below 4GiB; entry 1 therefore lives at address 0 and
+        // describes a buffer that straddles the 4GiB boundary.
+        mem.write_u32(0xFFFF_FFF8, 0x1000);
+        mem.write_u16(0xFFFF_FFFC, 2);
+        mem.write_u16(0xFFFF_FFFE, 0);
+        mem.write_u32(0, 0xFFFF_FFFE);
+        mem.write_u16(4, 4);
+        mem.write_u16(6, 0x8000);
+
+        let mut bm = BusMasterChannel::new();
+        bm.write(4, 4, 0xFFFF_FFF8);
+        bm.write(0, 1, 0x09);
+
+        let mut req = DmaRequest::ata_read(vec![1, 2, 3, 4, 5, 6]);
+        bm.execute_dma(&mut mem, &mut req).unwrap();
+
+        let mut out = [0u8; 2];
+        mem.read_physical(0x1000, &mut out);
+        assert_eq!(out, [1, 2]);
+        mem.read_physical(0xFFFF_FFFE, &mut out);
+        assert_eq!(out, [3, 4]);
+        mem.read_physical(0, &mut out);
+        assert_eq!(out, [5, 6]);
+        assert_eq!(mem.0.range(FOUR_GIB..).next(), None);
+    }
 }
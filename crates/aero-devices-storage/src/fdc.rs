@@ -0,0 +1,932 @@
+//! Intel 82077AA-compatible floppy disk controller (FDC).
+//!
+//! This models the subset of the 82077AA used by PC BIOSes, DOS tooling and the Windows/Linux
+//! floppy drivers:
+//! - AT-mode registers at `0x3F0..=0x3F5` and `0x3F7` (DOR, MSR/DSR, FIFO, DIR/CCR)
+//! - READ DATA, WRITE DATA, FORMAT TRACK, READ ID, SEEK, RECALIBRATE, SENSE INTERRUPT STATUS,
+//!   SENSE DRIVE STATUS, SPECIFY, CONFIGURE, LOCK, PERPENDICULAR MODE, DUMPREG and VERSION
+//! - data transfers on ISA DMA channel 2 and completion interrupts on IRQ6
+//!
+//! Only 1.44MB 3.5" media (80 cylinders, 2 heads, 18 sectors of 512 bytes) is supported, for up to
+//! two drives. Seeks complete instantly. Data phases run from [`FloppyController::tick`], which the
+//! platform calls with its [`Dma8237`] and guest memory; non-DMA (PIO) execution is not modeled.
+//!
+//! Media bytes are host state: snapshots capture controller/drive state only and `load_state`
+//! keeps any attached media.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_devices::dma::Dma8237;
+use aero_devices::irq::IrqLine;
+use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
+use aero_io_snapshot::io::state::{
+    IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
+};
+use aero_platform::io::{IoPortBus, PortIoDevice};
+use aero_storage::{VirtualDisk, SECTOR_SIZE};
+use memory::MemoryBus;
+
+/// Base I/O port of the primary floppy controller.
+pub const FDC_BASE: u16 = 0x3F0;
+/// ISA IRQ used by the floppy controller.
+pub const FDC_IRQ: u8 = 6;
+/// ISA DMA channel used by the floppy controller.
+pub const FDC_DMA_CHANNEL: usize = 2;
+
+/// 1.44MB media geometry.
+pub const FLOPPY_144_CYLINDERS: u8 = 80;
+pub const FLOPPY_144_HEADS: u8 = 2;
+pub const FLOPPY_144_SECTORS_PER_TRACK: u8 = 18;
+pub const FLOPPY_144_TOTAL_SECTORS: u64 = 2880;
+
+/// Number of drives the controller exposes.
+pub const FDC_DRIVES: usize = 2;
+
+const REG_SRA: u16 = 0;
+const REG_SRB: u16 = 1;
+const REG_DOR: u16 = 2;
+const REG_TDR: u16 = 3;
+const REG_MSR_DSR: u16 = 4;
+const REG_FIFO: u16 = 5;
+const REG_DIR_CCR: u16 = 7;
+
+const DOR_NRESET: u8 = 1 << 2;
+const DOR_DMA_GATE: u8 = 1 << 3;
+
+const MSR_CB: u8 = 1 << 4;
+const MSR_DIO: u8 = 1 << 6;
+const MSR_RQM: u8 = 1 << 7;
+
+const DSR_SW_RESET: u8 = 1 << 7;
+
+const DIR_DSKCHG: u8 = 1 << 7;
+
+const ST0_ABNORMAL: u8 = 0x40;
+const ST0_INVALID: u8 = 0x80;
+const ST0_READY_CHANGED: u8 = 0xC0;
+const ST0_SEEK_END: u8 = 0x20;
+const ST0_EQUIPMENT_CHECK: u8 = 0x10;
+
+const ST1_MISSING_AM: u8 = 0x01;
+const ST1_NOT_WRITABLE: u8 = 0x02;
+const ST1_NO_DATA: u8 = 0x04;
+const ST1_DATA_ERROR: u8 = 0x20;
+const ST1_END_OF_CYLINDER: u8 = 0x80;
+
+const ST3_TWO_SIDE: u8 = 0x08;
+const ST3_TRACK0: u8 = 0x10;
+const ST3_READY: u8 = 0x20;
+
+const CMD_READ_DATA: u8 = 0x06;
+const CMD_WRITE_DATA: u8 = 0x05;
+const CMD_FORMAT_TRACK: u8 = 0x0D;
+const CMD_READ_ID: u8 = 0x0A;
+const CMD_SPECIFY: u8 = 0x03;
+const CMD_SENSE_DRIVE_STATUS: u8 = 0x04;
+const CMD_RECALIBRATE: u8 = 0x07;
+const CMD_SENSE_INTERRUPT: u8 = 0x08;
+const CMD_DUMPREG: u8 = 0x0E;
+const CMD_SEEK: u8 = 0x0F;
+const CMD_VERSION: u8 = 0x10;
+const CMD_PERPENDICULAR: u8 = 0x12;
+const CMD_CONFIGURE: u8 = 0x13;
+const CMD_LOCK: u8 = 0x14;
+
+const CMD_FLAG_MT: u8 = 0x80;
+
+/// `VERSION` result byte identifying an enhanced (82077AA-class) controller.
+const VERSION_82077: u8 = 0x90;
+
+/// CONFIGURE bit 6: implied seek before READ/WRITE/FORMAT.
+const CONFIG_EIS: u8 = 0x40;
+/// Power-on CONFIGURE value (FIFO disabled, threshold 1, polling enabled).
+const CONFIG_DEFAULT: u8 = 0x20;
+
+/// Sector size code `N` for 512-byte sectors.
+const SECTOR_SIZE_CODE_512: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Command,
+    Execution,
+    Result,
+}
+
+impl Phase {
+    fn to_u8(self) -> u8 {
+        match self {
+            Phase::Command => 0,
+            Phase::Execution => 1,
+            Phase::Result => 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Phase::Command),
+            1 => Some(Phase::Execution),
+            2 => Some(Phase::Result),
+            _ => None,
+        }
+    }
+}
+
+/// Command parameter byte count (including the opcode byte), or `None` for invalid opcodes.
+fn command_len(opcode: u8) -> Option<usize> {
+    match opcode {
+        CMD_VERSION | CMD_DUMPREG | CMD_SENSE_INTERRUPT => return Some(1),
+        CMD_CONFIGURE => return Some(4),
+        CMD_PERPENDICULAR => return Some(2),
+        _ if opcode & 0x7F == CMD_LOCK => return Some(1),
+        _ => {}
+    }
+    match opcode & 0x1F {
+        CMD_READ_DATA | CMD_WRITE_DATA => Some(9),
+        CMD_FORMAT_TRACK => Some(6),
+        CMD_READ_ID | CMD_SENSE_DRIVE_STATUS | CMD_RECALIBRATE => Some(2),
+        CMD_SPECIFY | CMD_SEEK => Some(3),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct FloppyDrive {
+    cylinder: u8,
+    media: Option<Box<dyn VirtualDisk>>,
+    disk_changed: bool,
+}
+
+/// Sector address used by data commands.
+#[derive(Clone, Copy, Debug)]
+struct Chs {
+    c: u8,
+    h: u8,
+    r: u8,
+}
+
+impl Chs {
+    fn lba(self) -> Option<u64> {
+        if self.c >= FLOPPY_144_CYLINDERS
+            || self.h >= FLOPPY_144_HEADS
+            || self.r == 0
+            || self.r > FLOPPY_144_SECTORS_PER_TRACK
+        {
+            return None;
+        }
+        let track = u64::from(self.c) * u64::from(FLOPPY_144_HEADS) + u64::from(self.h);
+        Some(track * u64::from(FLOPPY_144_SECTORS_PER_TRACK) + u64::from(self.r - 1))
+    }
+}
+
+pub struct FloppyController {
+    dor: u8,
+    tdr: u8,
+    data_rate: u8,
+    phase: Phase,
+    command: Vec<u8>,
+    result: Vec<u8>,
+    result_pos: usize,
+    drives: [FloppyDrive; FDC_DRIVES],
+    /// SPECIFY parameters (step rate/head unload, head load/non-DMA).
+    specify: [u8; 2],
+    config: u8,
+    precomp: u8,
+    locked: bool,
+    perpendicular: u8,
+    /// Pending SENSE INTERRUPT STATUS results (ST0) per drive select (0..=3).
+    sense_pending: [Option<u8>; 4],
+    irq_pending: bool,
+    irq: Box<dyn IrqLine>,
+}
+
+impl FloppyController {
+    pub fn new(irq: Box<dyn IrqLine>) -> Self {
+        let mut fdc = Self {
+            dor: 0,
+            tdr: 0,
+            data_rate: 0,
+            phase: Phase::Command,
+            command: Vec::new(),
+            result: Vec::new(),
+            result_pos: 0,
+            drives: Default::default(),
+            specify: [0; 2],
+            config: CONFIG_DEFAULT,
+            precomp: 0,
+            locked: false,
+            perpendicular: 0,
+            sense_pending: [None; 4],
+            irq_pending: false,
+            irq,
+        };
+        fdc.reset();
+        fdc
+    }
+
+    /// Power-on reset. Attached media is preserved.
+    pub fn reset(&mut self) {
+        self.dor = 0;
+        self.tdr = 0;
+        self.data_rate = 0;
+        self.specify = [0; 2];
+        self.locked = false;
+        self.config = CONFIG_DEFAULT;
+        self.precomp = 0;
+        self.perpendicular = 0;
+        for drive in &mut self.drives {
+            drive.cylinder = 0;
+        }
+        self.controller_reset();
+    }
+
+    /// Insert media into `drive` (0 or 1), replacing any existing media.
+    pub fn attach_media(&mut self, drive: usize, media: Box<dyn VirtualDisk>) {
+        if let Some(d) = self.drives.get_mut(drive) {
+            d.media = Some(media);
+            d.disk_changed = true;
+        }
+    }
+
+    /// Remove media from `drive`, returning it.
+    pub fn eject_media(&mut self, drive: usize) -> Option<Box<dyn VirtualDisk>> {
+        let d = self.drives.get_mut(drive)?;
+        let media = d.media.take();
+        d.disk_changed = true;
+        media
+    }
+
+    pub fn has_media(&self, drive: usize) -> bool {
+        self.drives.get(drive).is_some_and(|d| d.media.is_some())
+    }
+
+    pub fn irq_level(&self) -> bool {
+        self.irq_pending && self.dor & DOR_DMA_GATE != 0
+    }
+
+    /// Whether a data command is waiting for [`FloppyController::tick`] to run its DMA phase.
+    pub fn transfer_pending(&self) -> bool {
+        self.phase == Phase::Execution
+    }
+
+    pub fn read_u8(&mut self, offset: u16) -> u8 {
+        match offset {
+            REG_SRA | REG_SRB => 0,
+            REG_DOR => self.dor,
+            REG_TDR => self.tdr,
+            REG_MSR_DSR => self.msr(),
+            REG_FIFO => self.read_fifo(),
+            REG_DIR_CCR => {
+                let drive = usize::from(self.dor & 3);
+                if self.drives.get(drive).is_some_and(|d| d.disk_changed) {
+                    DIR_DSKCHG
+                } else {
+                    0
+                }
+            }
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_u8(&mut self, offset: u16, value: u8) {
+        match offset {
+            REG_DOR => {
+                let was_reset = self.dor & DOR_NRESET == 0;
+                self.dor = value;
+                if value & DOR_NRESET == 0 {
+                    self.enter_reset();
+                } else if was_reset {
+                    self.controller_reset();
+                    self.raise_reset_interrupt();
+                }
+                self.update_irq();
+            }
+            REG_TDR => self.tdr = value & 0x03,
+            REG_MSR_DSR => {
+                self.data_rate = value & 0x03;
+                if value & DSR_SW_RESET != 0 {
+                    self.controller_reset();
+                    self.raise_reset_interrupt();
+                    self.update_irq();
+                }
+            }
+            REG_FIFO => self.write_fifo(value),
+            REG_DIR_CCR => self.data_rate = value & 0x03,
+            _ => {}
+        }
+    }
+
+    /// Run a pending READ/WRITE/FORMAT data phase over DMA channel 2.
+    ///
+    /// The transfer stalls (and is retried on the next tick) while the DMA channel is masked or the
+    /// DOR DMA gate is closed.
+    pub fn tick(&mut self, dma: &mut Dma8237, mem: &mut dyn MemoryBus) {
+        if self.phase != Phase::Execution
+            || self.dor & DOR_DMA_GATE == 0
+            || dma.channel_masked(FDC_DMA_CHANNEL)
+        {
+            return;
+        }
+
+        let opcode = self.command[0] & 0x1F;
+        let result = match opcode {
+            CMD_FORMAT_TRACK => self.execute_format(dma, mem),
+            _ => self.execute_read_write(opcode == CMD_WRITE_DATA, dma, mem),
+        };
+        self.finish_with_result(result, true);
+    }
+
+    fn msr(&self) -> u8 {
+        if self.dor & DOR_NRESET == 0 {
+            return 0;
+        }
+        match self.phase {
+            Phase::Command if self.command.is_empty() => MSR_RQM,
+            Phase::Command => MSR_RQM | MSR_CB,
+            Phase::Execution => MSR_CB,
+            Phase::Result => MSR_RQM | MSR_DIO | MSR_CB,
+        }
+    }
+
+    fn enter_reset(&mut self) {
+        self.phase = Phase::Command;
+        self.command.clear();
+        self.result.clear();
+        self.result_pos = 0;
+        self.irq_pending = false;
+        self.sense_pending = [None; 4];
+    }
+
+    fn controller_reset(&mut self) {
+        self.enter_reset();
+        if !self.locked {
+            self.config = CONFIG_DEFAULT;
+            self.precomp = 0;
+        }
+        self.update_irq();
+    }
+
+    fn raise_reset_interrupt(&mut self) {
+        // With polling enabled, the controller reports a "ready changed" status for each of the
+        // four drive selects; drivers issue four SENSE INTERRUPT STATUS commands after reset.
+        for (idx, pending) in self.sense_pending.iter_mut().enumerate() {
+            *pending = Some(ST0_READY_CHANGED | idx as u8);
+        }
+        self.irq_pending = true;
+    }
+
+    fn update_irq(&self) {
+        self.irq.set_level(self.irq_level());
+    }
+
+    fn raise_irq(&mut self) {
+        self.irq_pending = true;
+        self.update_irq();
+    }
+
+    fn lower_irq(&mut self) {
+        self.irq_pending = false;
+        self.update_irq();
+    }
+
+    fn read_fifo(&mut self) -> u8 {
+        if self.phase != Phase::Result {
+            return 0;
+        }
+        // Reading the result phase acknowledges the completion interrupt.
+        if self.result_pos == 0 {
+            self.lower_irq();
+        }
+        let value = self.result.get(self.result_pos).copied().unwrap_or(0);
+        self.result_pos += 1;
+        if self.result_pos >= self.result.len() {
+            self.result.clear();
+            self.result_pos = 0;
+            self.phase = Phase::Command;
+        }
+        value
+    }
+
+    fn write_fifo(&mut self, value: u8) {
+        if self.dor & DOR_NRESET == 0 || self.phase != Phase::Command {
+            return;
+        }
+        if self.command.is_empty() {
+            // Starting a new command clears any completion interrupt from the previous one.
+            // SENSE INTERRUPT STATUS is the exception: it is how seek interrupts are acknowledged.
+            if value != CMD_SENSE_INTERRUPT {
+                self.lower_irq();
+            }
+            if command_len(value).is_none() {
+                self.finish_with_result(vec![ST0_INVALID], false);
+                return;
+            }
+        }
+        self.command.push(value);
+        if Some(self.command.len()) == command_len(self.command[0]) {
+            self.execute_command();
+        }
+    }
+
+    fn finish_with_result(&mut self, result: Vec<u8>, interrupt: bool) {
+        self.command.clear();
+        self.result = result;
+        self.result_pos = 0;
+        self.phase = if self.result.is_empty() {
+            Phase::Command
+        } else {
+            Phase::Result
+        };
+        if interrupt {
+            self.raise_irq();
+        }
+    }
+
+    fn execute_command(&mut self) {
+        let cmd = self.command.clone();
+        let opcode = cmd[0];
+        match opcode {
+            CMD_VERSION => return self.finish_with_result(vec![VERSION_82077], false),
+            CMD_DUMPREG => {
+                let result = vec![
+                    self.drives[0].cylinder,
+                    self.drives[1].cylinder,
+                    0,
+                    0,
+                    self.specify[0],
+                    self.specify[1],
+                    FLOPPY_144_SECTORS_PER_TRACK,
+                    (u8::from(self.locked) << 7) | (self.perpendicular & 0x7F),
+                    self.config,
+                    self.precomp,
+                ];
+                return self.finish_with_result(result, false);
+            }
+            CMD_CONFIGURE => {
+                self.config = cmd[2];
+                self.precomp = cmd[3];
+                return self.finish_with_result(Vec::new(), false);
+            }
+            CMD_PERPENDICULAR => {
+                self.perpendicular = cmd[1];
+                return self.finish_with_result(Vec::new(), false);
+            }
+            _ if opcode & 0x7F == CMD_LOCK => {
+                self.locked = opcode & 0x80 != 0;
+                return self.finish_with_result(vec![u8::from(self.locked) << 4], false);
+            }
+            _ => {}
+        }
+
+        match opcode & 0x1F {
+            CMD_SPECIFY => {
+                self.specify = [cmd[1], cmd[2]];
+                self.finish_with_result(Vec::new(), false);
+            }
+            CMD_SENSE_DRIVE_STATUS => {
+                let select = cmd[1] & 0x07;
+                let drive = usize::from(select & 3);
+                let mut st3 = select | ST3_TWO_SIDE;
+                if let Some(d) = self.drives.get(drive) {
+                    st3 |= ST3_READY;
+                    if d.cylinder == 0 {
+                        st3 |= ST3_TRACK0;
+                    }
+                }
+                self.finish_with_result(vec![st3], false);
+            }
+            CMD_RECALIBRATE => self.seek(cmd[1], 0),
+            CMD_SEEK => self.seek(cmd[1], cmd[2]),
+            CMD_SENSE_INTERRUPT => {
+                let pending = self.sense_pending.iter().position(|st0| st0.is_some());
+                let result = match pending {
+                    Some(idx) => {
+                        let st0 = self.sense_pending[idx].take().unwrap_or(ST0_INVALID);
+                        let pcn = self.drives.get(idx).map_or(0, |d| d.cylinder);
+                        vec![st0, pcn]
+                    }
+                    None => vec![ST0_INVALID],
+                };
+                if self.sense_pending.iter().all(Option::is_none) {
+                    self.lower_irq();
+                }
+                self.finish_with_result(result, false);
+            }
+            CMD_READ_ID => {
+                let select = cmd[1] & 0x07;
+                let drive = usize::from(select & 3);
+                let head = (select >> 2) & 1;
+                let result = match self.drives.get(drive) {
+                    Some(d) if d.media.is_some() => {
+                        vec![select, 0, 0, d.cylinder, head, 1, SECTOR_SIZE_CODE_512]
+                    }
+                    _ => vec![
+                        ST0_ABNORMAL | select,
+                        ST1_MISSING_AM,
+                        0,
+                        0,
+                        head,
+                        1,
+                        SECTOR_SIZE_CODE_512,
+                    ],
+                };
+                self.finish_with_result(result, true);
+            }
+            CMD_READ_DATA | CMD_WRITE_DATA | CMD_FORMAT_TRACK => {
+                // The data phase runs from `tick()` once DMA is available.
+                self.phase = Phase::Execution;
+            }
+            _ => self.finish_with_result(vec![ST0_INVALID], false),
+        }
+    }
+
+    fn seek(&mut self, select: u8, cylinder: u8) {
+        let drive = usize::from(select & 3);
+        let head = select & 0x04;
+        let st0 = match self.drives.get_mut(drive) {
+            Some(d) => {
+                d.cylinder = cylinder.min(FLOPPY_144_CYLINDERS - 1);
+                // Stepping the heads with media present clears the disk-change line.
+                if d.media.is_some() {
+                    d.disk_changed = false;
+                }
+                ST0_SEEK_END | head | select & 3
+            }
+            None => ST0_ABNORMAL | ST0_SEEK_END | ST0_EQUIPMENT_CHECK | head | select & 3,
+        };
+        self.sense_pending[drive] = Some(st0);
+        self.finish_with_result(Vec::new(), true);
+    }
+
+    fn execute_read_write(
+        &mut self,
+        write: bool,
+        dma: &mut Dma8237,
+        mem: &mut dyn MemoryBus,
+    ) -> Vec<u8> {
+        let cmd = self.command.clone();
+        let multi_track = cmd[0] & CMD_FLAG_MT != 0;
+        let select = cmd[1] & 0x07;
+        let drive_idx = usize::from(select & 3);
+        let mut chs = Chs {
+            c: cmd[2],
+            h: cmd[3],
+            r: cmd[4],
+        };
+        let n = cmd[5];
+        let eot = cmd[6];
+        let implied_seek = self.config & CONFIG_EIS != 0;
+
+        let (mut st0, mut st1, st2) = (select & 0x07, 0u8, 0u8);
+        let result = |st0: u8, st1: u8, chs: Chs| vec![st0, st1, st2, chs.c, chs.h, chs.r, n];
+
+        let Some(drive) = self.drives.get_mut(drive_idx) else {
+            return result(ST0_ABNORMAL | ST0_EQUIPMENT_CHECK | st0, 0, chs);
+        };
+        if implied_seek {
+            drive.cylinder = chs.c.min(FLOPPY_144_CYLINDERS - 1);
+        }
+        let Some(media) = drive.media.as_mut() else {
+            return result(ST0_ABNORMAL | st0, ST1_MISSING_AM, chs);
+        };
+        if n != SECTOR_SIZE_CODE_512 || chs.c != drive.cylinder {
+            return result(ST0_ABNORMAL | st0, ST1_NO_DATA, chs);
+        }
+
+        let mut buf = [0u8; SECTOR_SIZE];
+        loop {
+            let Some(lba) = chs.lba() else {
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_NO_DATA;
+                break;
+            };
+            let offset = lba * SECTOR_SIZE as u64;
+            let xfer = if write {
+                buf.fill(0);
+                let xfer = dma.transfer_from_memory(FDC_DMA_CHANNEL, mem, &mut buf);
+                if media.write_at(offset, &buf).is_err() {
+                    st0 |= ST0_ABNORMAL;
+                    st1 |= ST1_NOT_WRITABLE;
+                    break;
+                }
+                xfer
+            } else {
+                if media.read_at(offset, &mut buf).is_err() {
+                    st0 |= ST0_ABNORMAL;
+                    st1 |= ST1_DATA_ERROR;
+                    break;
+                }
+                dma.transfer_to_memory(FDC_DMA_CHANNEL, mem, &buf)
+            };
+
+            // Advance to the next sector ID (this is what the result phase reports).
+            let end_of_track = chs.r >= eot;
+            if end_of_track {
+                chs.r = 1;
+                if multi_track && chs.h == 0 {
+                    chs.h = 1;
+                } else {
+                    chs.c = chs.c.wrapping_add(1);
+                    if multi_track {
+                        chs.h = 0;
+                    }
+                }
+            } else {
+                chs.r += 1;
+            }
+
+            if xfer.terminal_count || xfer.bytes < SECTOR_SIZE {
+                break;
+            }
+            if end_of_track && (chs.h == 0 || !multi_track) {
+                // Ran off the end of the cylinder without the DMA controller signalling TC.
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_END_OF_CYLINDER;
+                break;
+            }
+        }
+        if write {
+            let _ = media.flush();
+        }
+        result(st0, st1, chs)
+    }
+
+    fn execute_format(&mut self, dma: &mut Dma8237, mem: &mut dyn MemoryBus) -> Vec<u8> {
+        let cmd = self.command.clone();
+        let select = cmd[1] & 0x07;
+        let head = (select >> 2) & 1;
+        let drive_idx = usize::from(select & 3);
+        let n = cmd[2];
+        let sectors = cmd[3];
+        let filler = cmd[5];
+
+        let mut st0 = select;
+        let mut st1 = 0u8;
+        let mut last = Chs {
+            c: 0,
+            h: head,
+            r: 1,
+        };
+        let result = |st0: u8, st1: u8, chs: Chs| vec![st0, st1, 0, chs.c, chs.h, chs.r, n];
+
+        let Some(drive) = self.drives.get_mut(drive_idx) else {
+            return result(ST0_ABNORMAL | ST0_EQUIPMENT_CHECK | st0, 0, last);
+        };
+        last.c = drive.cylinder;
+        let Some(media) = drive.media.as_mut() else {
+            return result(ST0_ABNORMAL | st0, ST1_MISSING_AM, last);
+        };
+        if n != SECTOR_SIZE_CODE_512 {
+            return result(ST0_ABNORMAL | st0, ST1_NO_DATA, last);
+        }
+
+        let data = [filler; SECTOR_SIZE];
+        for _ in 0..sectors {
+            // Each sector is described by a 4-byte ID field (C, H, R, N) supplied over DMA.
+            let mut id = [0u8; 4];
+            let xfer = dma.transfer_from_memory(FDC_DMA_CHANNEL, mem, &mut id);
+            if xfer.bytes < id.len() {
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_NO_DATA;
+                break;
+            }
+            last = Chs {
+                c: id[0],
+                h: id[1],
+                r: id[2],
+            };
+            let Some(lba) = last.lba() else {
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_NO_DATA;
+                break;
+            };
+            if media.write_at(lba * SECTOR_SIZE as u64, &data).is_err() {
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_NOT_WRITABLE;
+                break;
+            }
+            if xfer.terminal_count {
+                break;
+            }
+        }
+        let _ = media.flush();
+        result(st0, st1, last)
+    }
+}
+
+impl IoSnapshot for FloppyController {
+    const DEVICE_ID: [u8; 4] = *b"FDC7";
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+
+    fn save_state(&self) -> Vec<u8> {
+        const TAG_DOR: u16 = 1;
+        const TAG_TDR: u16 = 2;
+        const TAG_DATA_RATE: u16 = 3;
+        const TAG_PHASE: u16 = 4;
+        const TAG_COMMAND: u16 = 5;
+        const TAG_RESULT: u16 = 6;
+        const TAG_DRIVES: u16 = 7;
+        const TAG_SPECIFY: u16 = 8;
+        const TAG_CONFIG: u16 = 9;
+        const TAG_PRECOMP: u16 = 10;
+        const TAG_LOCKED: u16 = 11;
+        const TAG_PERPENDICULAR: u16 = 12;
+        const TAG_SENSE_PENDING: u16 = 13;
+        const TAG_IRQ_PENDING: u16 = 14;
+
+        let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
+        w.field_u8(TAG_DOR, self.dor);
+        w.field_u8(TAG_TDR, self.tdr);
+        w.field_u8(TAG_DATA_RATE, self.data_rate);
+        w.field_u8(TAG_PHASE, self.phase.to_u8());
+        w.field_bytes(TAG_COMMAND, Encoder::new().vec_u8(&self.command).finish());
+        w.field_bytes(
+            TAG_RESULT,
+            Encoder::new()
+                .vec_u8(&self.result[self.result_pos.min(self.result.len())..])
+                .finish(),
+        );
+
+        let mut enc = Encoder::new();
+        for drive in &self.drives {
+            enc = enc.u8(drive.cylinder).bool(drive.disk_changed);
+        }
+        w.field_bytes(TAG_DRIVES, enc.finish());
+
+        w.field_bytes(TAG_SPECIFY, self.specify.to_vec());
+        w.field_u8(TAG_CONFIG, self.config);
+        w.field_u8(TAG_PRECOMP, self.precomp);
+        w.field_bool(TAG_LOCKED, self.locked);
+        w.field_u8(TAG_PERPENDICULAR, self.perpendicular);
+
+        let mut enc = Encoder::new();
+        for pending in &self.sense_pending {
+            enc = match pending {
+                Some(st0) => enc.bool(true).u8(*st0),
+                None => enc.bool(false),
+            };
+        }
+        w.field_bytes(TAG_SENSE_PENDING, enc.finish());
+        w.field_bool(TAG_IRQ_PENDING, self.irq_pending);
+        w.finish()
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
+        const TAG_DOR: u16 = 1;
+        const TAG_TDR: u16 = 2;
+        const TAG_DATA_RATE: u16 = 3;
+        const TAG_PHASE: u16 = 4;
+        const TAG_COMMAND: u16 = 5;
+        const TAG_RESULT: u16 = 6;
+        const TAG_DRIVES: u16 = 7;
+        const TAG_SPECIFY: u16 = 8;
+        const TAG_CONFIG: u16 = 9;
+        const TAG_PRECOMP: u16 = 10;
+        const TAG_LOCKED: u16 = 11;
+        const TAG_PERPENDICULAR: u16 = 12;
+        const TAG_SENSE_PENDING: u16 = 13;
+        const TAG_IRQ_PENDING: u16 = 14;
+
+        let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
+        r.ensure_device_major(Self::DEVICE_VERSION.major)?;
+
+        // Reset to a deterministic baseline while keeping the host-attached media.
+        self.reset();
+        for drive in &mut self.drives {
+            drive.disk_changed = false;
+        }
+
+        self.dor = r.u8(TAG_DOR)?.unwrap_or(0);
+        self.tdr = r.u8(TAG_TDR)?.unwrap_or(0);
+        self.data_rate = r.u8(TAG_DATA_RATE)?.unwrap_or(0);
+        if let Some(phase) = r.u8(TAG_PHASE)? {
+            self.phase =
+                Phase::from_u8(phase).ok_or(SnapshotError::InvalidFieldEncoding("phase"))?;
+        }
+        if let Some(buf) = r.bytes(TAG_COMMAND) {
+            let mut d = Decoder::new(buf);
+            self.command = d.vec_u8()?;
+            d.finish()?;
+            if self.command.len() > 9 {
+                return Err(SnapshotError::InvalidFieldEncoding("command"));
+            }
+        }
+        if let Some(buf) = r.bytes(TAG_RESULT) {
+            let mut d = Decoder::new(buf);
+            self.result = d.vec_u8()?;
+            d.finish()?;
+            if self.result.len() > 10 {
+                return Err(SnapshotError::InvalidFieldEncoding("result"));
+            }
+        }
+        let command_complete = self
+            .command
+            .first()
+            .and_then(|&op| command_len(op))
+            .is_some_and(|len| len == self.command.len());
+        let consistent = match self.phase {
+            Phase::Command => !command_complete,
+            Phase::Execution => command_complete,
+            Phase::Result => !self.result.is_empty(),
+        };
+        if !consistent {
+            return Err(SnapshotError::InvalidFieldEncoding("phase"));
+        }
+
+        if let Some(buf) = r.bytes(TAG_DRIVES) {
+            let mut d = Decoder::new(buf);
+            for drive in &mut self.drives {
+                drive.cylinder = d.u8()?;
+                drive.disk_changed = d.bool()?;
+            }
+            d.finish()?;
+        }
+        if let Some(buf) = r.bytes(TAG_SPECIFY) {
+            let mut d = Decoder::new(buf);
+            self.specify = [d.u8()?, d.u8()?];
+            d.finish()?;
+        }
+        self.config = r.u8(TAG_CONFIG)?.unwrap_or(CONFIG_DEFAULT);
+        self.precomp = r.u8(TAG_PRECOMP)?.unwrap_or(0);
+        self.locked = r.bool(TAG_LOCKED)?.unwrap_or(false);
+        self.perpendicular = r.u8(TAG_PERPENDICULAR)?.unwrap_or(0);
+        if let Some(buf) = r.bytes(TAG_SENSE_PENDING) {
+            let mut d = Decoder::new(buf);
+            for pending in &mut self.sense_pending {
+                *pending = if d.bool()? { Some(d.u8()?) } else { None };
+            }
+            d.finish()?;
+        }
+        self.irq_pending = r.bool(TAG_IRQ_PENDING)?.unwrap_or(false);
+
+        // Re-drive the IRQ line from the restored latch.
+        self.update_irq();
+        Ok(())
+    }
+}
+
+pub type SharedFloppyController = Rc<RefCell<FloppyController>>;
+
+/// I/O-port view of a shared [`FloppyController`].
+pub struct FloppyControllerPort {
+    fdc: SharedFloppyController,
+    port: u16,
+}
+
+impl FloppyControllerPort {
+    pub fn new(fdc: SharedFloppyController, port: u16) -> Self {
+        Self { fdc, port }
+    }
+}
+
+impl PortIoDevice for FloppyControllerPort {
+    fn read(&mut self, port: u16, size: u8) -> u32 {
+        debug_assert_eq!(port, self.port);
+        if size == 0 {
+            return 0;
+        }
+        // The FDC is an 8-bit ISA device; wider reads return the byte in the low lane.
+        let value = self.fdc.borrow_mut().read_u8(port.wrapping_sub(FDC_BASE));
+        match size {
+            1 => u32::from(value),
+            2 => u32::from(value) | 0xFF00,
+            _ => u32::from(value) | 0xFFFF_FF00,
+        }
+    }
+
+    fn write(&mut self, port: u16, size: u8, value: u32) {
+        debug_assert_eq!(port, self.port);
+        if size == 0 {
+            return;
+        }
+        self.fdc
+            .borrow_mut()
+            .write_u8(port.wrapping_sub(FDC_BASE), value as u8);
+    }
+
+    fn reset(&mut self) {
+        self.fdc.borrow_mut().reset();
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "fdc"
+    }
+}
+
+/// Register the floppy controller at `0x3F0..=0x3F5` and `0x3F7`.
+///
+/// `0x3F6` belongs to the primary IDE channel's control block and is left untouched.
+pub fn register_floppy_controller(bus: &mut IoPortBus, fdc: SharedFloppyController) {
+    for offset in [
+        REG_SRA,
+        REG_SRB,
+        REG_DOR,
+        REG_TDR,
+        REG_MSR_DSR,
+        REG_FIFO,
+        REG_DIR_CCR,
+    ] {
+        let port = FDC_BASE + offset;
+        bus.register(port, Box::new(FloppyControllerPort::new(fdc.clone(), port)));
+    }
+}
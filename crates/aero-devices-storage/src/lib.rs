@@ -3,11 +3,13 @@
 //! This crate intentionally models the *device-side* behaviour of:
 //! - Legacy IDE (ATA PIO) via I/O ports (`0x1F0/0x3F6`, `0x170/0x376`)
 //! - AHCI (SATA) via HBA memory registers and command list DMA
+//! - Legacy floppy (82077AA FDC) via I/O ports (`0x3F0..=0x3F7`) and ISA DMA channel 2
 //!
 //! The goal is to provide enough fidelity for early boot (e.g. FreeDOS via IDE)
 //! and Windows 7 boot (AHCI via `msahci.sys`) when wired into a full emulator.
 
 pub mod ahci;
+pub mod fdc;
 pub mod ide;
 
 pub mod ata;
@@ -15,6 +17,7 @@ pub mod bus;
 pub mod pci_ahci;
 
 pub use aero_devices::irq::IrqLine;
+pub use fdc::FloppyController;
 pub use pci_ahci::AhciPciDevice;
 
 pub use memory::MemoryBus;
@@ -11,6 +11,7 @@ use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
 use aero_platform::interrupts::msi::MsiTrigger;
+use aero_storage::StorageFaultInjector;
 use memory::MemoryBus;
 use memory::MmioHandler;
 
@@ -110,11 +111,49 @@ impl AhciPciDevice {
         self.controller.detach_drive(port);
     }
 
+    /// Hot-plug a drive into the given port. See [`AhciController::hot_attach_drive`].
+    pub fn hot_attach_drive(&mut self, port: usize, drive: AtaDrive) {
+        self.controller.hot_attach_drive(port, drive);
+        self.service_interrupts();
+    }
+
+    /// Surprise-remove the drive on the given port. See [`AhciController::hot_detach_drive`].
+    pub fn hot_detach_drive(&mut self, port: usize) {
+        self.controller.hot_detach_drive(port);
+        self.service_interrupts();
+    }
+
+    /// Signal a device change (e.g. capacity growth) on the given port to the guest.
+    ///
+    /// See [`AhciController::notify_device_changed`].
+    pub fn notify_device_changed(&mut self, port: usize) {
+        self.controller.notify_device_changed(port);
+        self.service_interrupts();
+    }
+
     /// Returns whether a drive backend is currently attached to the given AHCI port.
     pub fn drive_attached(&self, port: usize) -> bool {
         self.controller.drive_attached(port)
     }
 
+    /// See [`AhciController::set_fault_injector`].
+    pub fn set_fault_injector(&mut self, injector: Option<StorageFaultInjector>) {
+        self.controller.set_fault_injector(injector);
+    }
+
+    pub fn fault_injector(&self) -> Option<&StorageFaultInjector> {
+        self.controller.fault_injector()
+    }
+
+    /// See [`AhciController::set_time_ns`].
+    pub fn set_time_ns(&mut self, now_ns: u64) {
+        self.controller.set_time_ns(now_ns);
+    }
+
+    pub fn deferred_command_count(&self) -> usize {
+        self.controller.deferred_command_count()
+    }
+
     /// Reset the device back to its power-on state while preserving attached drives.
     ///
     /// This is intended for machine/platform reset flows where host-provided disk backends should
@@ -786,11 +825,12 @@ mod tests {
             "byte write must only clear bits covered by the written byte"
         );
 
-        // Clear the upper 2 bytes of PxIS with a 2-byte write.
+        // Clear the upper 2 bytes of PxIS with a 2-byte write. PRCS (bit 22) mirrors
+        // PxSERR.DIAG.N and is not W1C.
         dev.mmio_write(px_is_off + 2, 2, 0xFFFF);
         assert_eq!(
             dev.mmio_read(px_is_off, 4),
-            0x0000_00FF,
+            0x0040_00FF,
             "2-byte write must only clear bits covered by the written bytes"
         );
 
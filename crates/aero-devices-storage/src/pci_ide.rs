@@ -17,10 +17,10 @@ use aero_io_snapshot::io::storage::state::{
     IdeTaskFileState, IdeTransferKind, PciConfigSpaceState, MAX_IDE_DATA_BUFFER_BYTES,
 };
 use aero_platform::io::{IoPortBus, PortIoDevice};
-use aero_storage::SECTOR_SIZE;
+use aero_storage::{StorageFaultInjector, StorageRequest, StorageRequestKind, SECTOR_SIZE};
 use memory::MemoryBus;
 
-use crate::ata::AtaDrive;
+use crate::ata::{ata_error_for_disk_error, ata_error_for_io_error, AtaDrive};
 use crate::atapi::{AtapiCdrom, IsoBackend, PacketResult};
 use crate::busmaster::{BusMasterChannel, DmaCommit, DmaRequest};
 
@@ -321,6 +321,9 @@ struct Channel {
 
     // Latched parameters for an in-flight ATA PIO write command.
     pio_write: Option<(u64, u64)>,
+
+    // Guest time until which the current command appears busy because of an injected delay.
+    held_until_ns: Option<u64>,
 }
 
 impl Channel {
@@ -341,6 +344,7 @@ impl Channel {
             irq_pending: false,
             pending_dma: None,
             pio_write: None,
+            held_until_ns: None,
         }
     }
 
@@ -391,9 +395,47 @@ impl Channel {
         self.data_index = 0;
         self.pending_dma = None;
         self.pio_write = None;
+        self.held_until_ns = None;
         self.clear_irq();
     }
 
+    /// Status as seen by the guest: an injected delay keeps the command busy.
+    fn visible_status(&self) -> u8 {
+        if self.held_until_ns.is_some() {
+            (self.status & !IDE_STATUS_DRQ) | IDE_STATUS_BSY
+        } else {
+            self.status
+        }
+    }
+
+    /// The disk request `cmd` will issue to the selected ATA drive, if any.
+    fn storage_request(&mut self, cmd: u8) -> Option<StorageRequest> {
+        let dev_idx = self.selected_drive() as usize;
+        if !matches!(self.devices[dev_idx], Some(IdeDevice::Ata(_))) {
+            return None;
+        }
+        let kind = match cmd {
+            0x20 | 0x24 | 0xC8 | 0x25 => StorageRequestKind::Read,
+            0x30 | 0x34 | 0xCA | 0x35 => StorageRequestKind::Write,
+            0xE7 | 0xEA => {
+                return Some(StorageRequest {
+                    kind: StorageRequestKind::Flush,
+                    lba: 0,
+                    sectors: 0,
+                })
+            }
+            _ => return None,
+        };
+        let is_lba48 = matches!(cmd, 0x24 | 0x34 | 0x25 | 0x35);
+        self.tf.normalize_for_command(is_lba48);
+        let (lba, sectors) = if is_lba48 {
+            (self.tf.lba48(), u64::from(self.tf.sector_count48()))
+        } else {
+            (self.tf.lba28(), u64::from(self.tf.sector_count28()))
+        };
+        Some(StorageRequest { kind, lba, sectors })
+    }
+
     fn set_error(&mut self, err: u8) {
         self.error = err;
         self.status |= IDE_STATUS_ERR;
@@ -600,6 +642,10 @@ pub struct IdeController {
     secondary: Channel,
     bus_master_base: u16,
     bus_master: [BusMasterChannel; 2],
+    /// Optional latency/fault injection (see [`IdeController::set_fault_injector`]). Host
+    /// configuration, not guest state.
+    fault_injector: Option<StorageFaultInjector>,
+    now_ns: u64,
 }
 
 impl IdeController {
@@ -609,6 +655,8 @@ impl IdeController {
             secondary: Channel::new(SECONDARY_PORTS),
             bus_master_base,
             bus_master: [BusMasterChannel::new(), BusMasterChannel::new()],
+            fault_injector: None,
+            now_ns: 0,
         }
     }
     /// Reset the controller's guest-visible register/state machine back to its power-on baseline,
@@ -637,6 +685,43 @@ impl IdeController {
         self.bus_master_base
     }
 
+    /// Attach (or detach) a storage fault injector consulted when the guest issues an ATA
+    /// read/write/flush command.
+    ///
+    /// IDE has a single outstanding command per channel, so an injected delay keeps the channel
+    /// BSY (with its interrupt and any DMA held back) until [`IdeController::set_time_ns`] passes
+    /// the due time. Detaching the injector releases held commands immediately. Holds are not
+    /// part of snapshots.
+    pub fn set_fault_injector(&mut self, injector: Option<StorageFaultInjector>) {
+        self.fault_injector = injector;
+        if self.fault_injector.is_none() {
+            self.primary.held_until_ns = None;
+            self.secondary.held_until_ns = None;
+        }
+    }
+
+    pub fn fault_injector(&self) -> Option<&StorageFaultInjector> {
+        self.fault_injector.as_ref()
+    }
+
+    /// Update the guest time used for injected delays, releasing commands that are now due.
+    pub fn set_time_ns(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
+        for chan in [&mut self.primary, &mut self.secondary] {
+            if chan.held_until_ns.is_some_and(|due| due <= now_ns) {
+                chan.held_until_ns = None;
+            }
+        }
+    }
+
+    /// Number of channels whose current command is held by an injected delay.
+    pub fn held_command_count(&self) -> usize {
+        [&self.primary, &self.secondary]
+            .iter()
+            .filter(|chan| chan.held_until_ns.is_some())
+            .count()
+    }
+
     pub fn set_bus_master_base(&mut self, base: u16) {
         self.bus_master_base = base;
     }
@@ -813,13 +898,15 @@ impl IdeController {
         let off = port.wrapping_sub(self.primary.ports.cmd_base);
         if off < 8 {
             let reg = off;
-            Self::write_cmd_reg(&mut self.primary, reg, size, val);
+            let injector = self.fault_injector.as_mut();
+            Self::write_cmd_reg(&mut self.primary, reg, size, val, injector, self.now_ns);
             return;
         }
         let off = port.wrapping_sub(self.secondary.ports.cmd_base);
         if off < 8 {
             let reg = off;
-            Self::write_cmd_reg(&mut self.secondary, reg, size, val);
+            let injector = self.fault_injector.as_mut();
+            Self::write_cmd_reg(&mut self.secondary, reg, size, val, injector, self.now_ns);
             return;
         }
 
@@ -870,15 +957,25 @@ impl IdeController {
                 chan.tf.read_reg(reg, hob) as u32
             }
             ATA_REG_STATUS_COMMAND => {
-                // Reading STATUS clears pending IRQ.
-                chan.clear_irq();
-                chan.status as u32
+                // Reading STATUS clears pending IRQ (which cannot be raised yet while a command is
+                // held busy).
+                if chan.held_until_ns.is_none() {
+                    chan.clear_irq();
+                }
+                chan.visible_status() as u32
             }
             _ => 0,
         }
     }
 
-    fn write_cmd_reg(chan: &mut Channel, reg: u16, size: u8, val: u32) {
+    fn write_cmd_reg(
+        chan: &mut Channel,
+        reg: u16,
+        size: u8,
+        val: u32,
+        injector: Option<&mut StorageFaultInjector>,
+        now_ns: u64,
+    ) {
         // Writes only affect the currently-selected device. If it is absent, ignore writes so a
         // guest probing for a slave device does not accidentally perturb the master device's
         // taskfile register image.
@@ -905,7 +1002,7 @@ impl IdeController {
                 chan.tf.write_reg(reg, val as u8);
             }
             ATA_REG_STATUS_COMMAND => {
-                Self::exec_command(chan, val as u8);
+                Self::issue_command(chan, val as u8, injector, now_ns);
             }
             _ => {}
         }
@@ -923,7 +1020,7 @@ impl IdeController {
         }
 
         match reg {
-            ATA_CTRL_ALT_STATUS_DEVICE_CTRL => chan.status as u32,
+            ATA_CTRL_ALT_STATUS_DEVICE_CTRL => chan.visible_status() as u32,
             ATA_CTRL_DRIVE_ADDRESS => chan.drive_address() as u32,
             _ => 0,
         }
@@ -941,6 +1038,33 @@ impl IdeController {
         }
     }
 
+    fn issue_command(
+        chan: &mut Channel,
+        cmd: u8,
+        injector: Option<&mut StorageFaultInjector>,
+        now_ns: u64,
+    ) {
+        let outcome = match (injector, chan.storage_request(cmd)) {
+            (Some(injector), Some(req)) => injector.on_request(req),
+            _ => {
+                Self::exec_command(chan, cmd);
+                return;
+            }
+        };
+        match outcome.error {
+            Some(err) => {
+                chan.status |= IDE_STATUS_BSY;
+                chan.status &= !IDE_STATUS_DRQ;
+                chan.clear_irq();
+                chan.abort_command(ata_error_for_disk_error(&err.to_disk_error()));
+            }
+            None => Self::exec_command(chan, cmd),
+        }
+        if outcome.delay_ns != 0 {
+            chan.held_until_ns = Some(now_ns.saturating_add(outcome.delay_ns));
+        }
+    }
+
     fn exec_command(chan: &mut Channel, cmd: u8) {
         chan.status |= IDE_STATUS_BSY;
         chan.status &= !IDE_STATUS_DRQ;
@@ -995,14 +1119,15 @@ impl IdeController {
                 };
 
                 let res = match chan.devices[dev_idx].as_mut() {
-                    Some(IdeDevice::Ata(dev)) => ata_pio_read(dev, lba, sectors).ok(),
-                    _ => None,
+                    Some(IdeDevice::Ata(dev)) => {
+                        ata_pio_read(dev, lba, sectors).map_err(|e| ata_error_for_io_error(&e))
+                    }
+                    _ => Err(0x04),
                 };
 
-                if let Some(buf) = res {
-                    chan.begin_pio_in(TransferKind::AtaPioRead, buf);
-                } else {
-                    chan.abort_command(0x04);
+                match res {
+                    Ok(buf) => chan.begin_pio_in(TransferKind::AtaPioRead, buf),
+                    Err(err) => chan.abort_command(err),
                 }
             }
             0x30 | 0x34 => {
@@ -1046,25 +1171,28 @@ impl IdeController {
                                 .and_then(|v| usize::try_from(v).ok())
                                 .filter(|&v| v <= MAX_IDE_DATA_BUFFER_BYTES);
 
-                            byte_len.and_then(|len| {
-                                try_alloc_zeroed(len)
-                                    .map(|buf| DmaRequest::ata_write(buf, lba, sectors))
-                            })
+                            byte_len
+                                .and_then(|len| {
+                                    try_alloc_zeroed(len)
+                                        .map(|buf| DmaRequest::ata_write(buf, lba, sectors))
+                                })
+                                .ok_or(0x04)
                         } else {
                             ata_pio_read(dev, lba, sectors)
-                                .ok()
                                 .map(DmaRequest::ata_read)
+                                .map_err(|e| ata_error_for_io_error(&e))
                         }
                     }
-                    _ => None,
+                    _ => Err(0x04),
                 };
 
-                if let Some(req) = req {
-                    chan.pending_dma = Some(req);
-                    chan.status &= !IDE_STATUS_BSY;
-                    chan.status |= IDE_STATUS_DRDY;
-                } else {
-                    chan.abort_command(0x04);
+                match req {
+                    Ok(req) => {
+                        chan.pending_dma = Some(req);
+                        chan.status &= !IDE_STATUS_BSY;
+                        chan.status |= IDE_STATUS_DRDY;
+                    }
+                    Err(err) => chan.abort_command(err),
                 }
             }
             0xE7 | 0xEA => {
@@ -1132,7 +1260,7 @@ impl IdeController {
     }
 
     fn tick_channel(bm: &mut BusMasterChannel, chan: &mut Channel, mem: &mut dyn MemoryBus) {
-        if !bm.is_started() {
+        if !bm.is_started() || chan.held_until_ns.is_some() {
             return;
         }
         let Some(mut req) = chan.pending_dma.take() else {
@@ -1140,7 +1268,7 @@ impl IdeController {
         };
 
         match bm.execute_dma(mem, &mut req) {
-            Ok(()) => {
+            Ok(completion) => {
                 let mut ok = true;
                 // Commit writes after the DMA engine has pulled data from guest memory.
                 if let Some(DmaCommit::AtaWrite { lba, sectors }) = req.commit.take() {
@@ -1154,7 +1282,7 @@ impl IdeController {
                 }
 
                 if ok {
-                    bm.finish_success();
+                    bm.finish_success(completion);
                     // For ATAPI DMA commands, transition to status phase (interrupt reason).
                     let dev_idx = chan.selected_drive() as usize;
                     if matches!(chan.devices[dev_idx], Some(IdeDevice::Atapi(_))) {
@@ -1179,11 +1307,15 @@ impl IdeController {
     }
 
     pub fn primary_irq_pending(&self) -> bool {
-        self.primary.irq_pending && (self.primary.control & IDE_CTRL_NIEN) == 0
+        self.primary.irq_pending
+            && (self.primary.control & IDE_CTRL_NIEN) == 0
+            && self.primary.held_until_ns.is_none()
     }
 
     pub fn secondary_irq_pending(&self) -> bool {
-        self.secondary.irq_pending && (self.secondary.control & IDE_CTRL_NIEN) == 0
+        self.secondary.irq_pending
+            && (self.secondary.control & IDE_CTRL_NIEN) == 0
+            && self.secondary.held_until_ns.is_none()
     }
 }
 
@@ -1656,6 +1788,10 @@ impl PortIoDevice for Piix3IdePort {
         debug_assert_eq!(port, self.port);
         self.ide.borrow_mut().io_write(port, size, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "piix3-ide"
+    }
 }
 
 /// Register the PIIX3 IDE controller's legacy ports + Bus Master IDE BAR on an [`IoPortBus`].
@@ -1699,12 +1835,12 @@ mod tests {
     }
 
     impl IsoBackend for TestIsoBackend {
-        fn sector_count(&self) -> u32 {
+        fn sector_count(&self) -> u64 {
             let sectors = self.image.len() / AtapiCdrom::SECTOR_SIZE;
-            sectors as u32
+            sectors as u64
         }
 
-        fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+        fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
             if !buf.len().is_multiple_of(AtapiCdrom::SECTOR_SIZE) {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
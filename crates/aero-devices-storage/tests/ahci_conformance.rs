@@ -0,0 +1,401 @@
+//! Register-level conformance checks against the AHCI 1.3 software initialization and port
+//! start/stop sequences (§10.1.2, §10.3.1, §10.3.2).
+
+use aero_devices::pci::PciDevice as _;
+use aero_devices_storage::ata::{
+    AtaDrive, ATA_CMD_IDENTIFY, ATA_STATUS_BSY, ATA_STATUS_DRDY, ATA_STATUS_DRQ, ATA_STATUS_DSC,
+};
+use aero_devices_storage::AhciPciDevice;
+use aero_storage::{MemBackend, RawDisk, SECTOR_SIZE};
+use memory::{Bus, MemoryBus};
+
+const HBA_CAP: u64 = 0x00;
+const HBA_GHC: u64 = 0x04;
+const HBA_IS: u64 = 0x08;
+const HBA_PI: u64 = 0x0C;
+const HBA_CAP2: u64 = 0x24;
+
+const PORT_BASE: u64 = 0x100;
+
+const PORT_REG_CLB: u64 = 0x00;
+const PORT_REG_CLBU: u64 = 0x04;
+const PORT_REG_FB: u64 = 0x08;
+const PORT_REG_FBU: u64 = 0x0C;
+const PORT_REG_IS: u64 = 0x10;
+const PORT_REG_IE: u64 = 0x14;
+const PORT_REG_CMD: u64 = 0x18;
+const PORT_REG_TFD: u64 = 0x20;
+const PORT_REG_SIG: u64 = 0x24;
+const PORT_REG_SSTS: u64 = 0x28;
+const PORT_REG_SCTL: u64 = 0x2C;
+const PORT_REG_SERR: u64 = 0x30;
+const PORT_REG_SACT: u64 = 0x34;
+const PORT_REG_CI: u64 = 0x38;
+
+const CAP_S64A: u32 = 1 << 31;
+const CAP_SNCQ: u32 = 1 << 30;
+const CAP_SSS: u32 = 1 << 27;
+const CAP_SCLO: u32 = 1 << 24;
+const CAP_SAM: u32 = 1 << 18;
+const CAP_SPM: u32 = 1 << 17;
+const CAP_NCS_MASK: u32 = 0x1F << 8;
+
+const GHC_IE: u32 = 1 << 1;
+const GHC_AE: u32 = 1 << 31;
+
+const PORT_CMD_ST: u32 = 1 << 0;
+const PORT_CMD_SUD: u32 = 1 << 1;
+const PORT_CMD_POD: u32 = 1 << 2;
+const PORT_CMD_CLO: u32 = 1 << 3;
+const PORT_CMD_FRE: u32 = 1 << 4;
+const PORT_CMD_CCS_MASK: u32 = 0x1F << 8;
+const PORT_CMD_FR: u32 = 1 << 14;
+const PORT_CMD_CR: u32 = 1 << 15;
+
+const PORT_IS_DHRS: u32 = 1 << 0;
+const PORT_IS_PCS: u32 = 1 << 6;
+const PORT_IS_PRCS: u32 = 1 << 22;
+
+const SERR_DIAG_N: u32 = 1 << 16;
+const SERR_DIAG_X: u32 = 1 << 26;
+
+const SSTS_DET_MASK: u32 = 0xF;
+const SATA_SIG_ATA: u32 = 0x0000_0101;
+
+const CLB: u64 = 0x1_0000;
+const FB: u64 = 0x1_1000;
+const CTBA: u64 = 0x1_2000;
+const DATA_BUF: u64 = 0x1_3000;
+
+fn drive() -> AtaDrive {
+    let disk = RawDisk::create(MemBackend::new(), 64 * SECTOR_SIZE as u64).unwrap();
+    AtaDrive::new(Box::new(disk)).unwrap()
+}
+
+fn device(with_drive: bool) -> AhciPciDevice {
+    let mut dev = AhciPciDevice::new(1);
+    if with_drive {
+        dev.attach_drive(0, drive());
+    }
+    dev.config_mut().set_command(0x0006); // MEM + BUSMASTER
+    dev
+}
+
+fn read(dev: &mut AhciPciDevice, offset: u64) -> u32 {
+    dev.mmio_read(offset, 4) as u32
+}
+
+fn write(dev: &mut AhciPciDevice, offset: u64, val: u32) {
+    dev.mmio_write(offset, 4, u64::from(val));
+}
+
+/// AHCI 1.3 §10.1.2 steps 1-7 for port 0, asserting the register state software relies on.
+fn init_hba(dev: &mut AhciPciDevice) {
+    // 1. Indicate that system software is AHCI aware.
+    write(dev, HBA_GHC, GHC_AE);
+    assert_ne!(read(dev, HBA_GHC) & GHC_AE, 0);
+
+    // 2. Determine which ports are implemented.
+    assert_eq!(read(dev, HBA_PI), 0b1);
+
+    // 3. Ensure the port is idle.
+    let cmd = read(dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(
+        cmd & (PORT_CMD_ST | PORT_CMD_CR | PORT_CMD_FRE | PORT_CMD_FR),
+        0
+    );
+
+    // 4. Determine the number of command slots.
+    let cap = read(dev, HBA_CAP);
+    assert_eq!((cap & CAP_NCS_MASK) >> 8, 31);
+    assert_ne!(cap & CAP_S64A, 0);
+
+    // 5. Program the command list / received FIS areas, then enable FIS receive.
+    write(dev, PORT_BASE + PORT_REG_CLB, CLB as u32);
+    write(dev, PORT_BASE + PORT_REG_CLBU, (CLB >> 32) as u32);
+    write(dev, PORT_BASE + PORT_REG_FB, FB as u32);
+    write(dev, PORT_BASE + PORT_REG_FBU, (FB >> 32) as u32);
+    write(dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE);
+    let cmd = read(dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(
+        cmd & (PORT_CMD_FRE | PORT_CMD_FR),
+        PORT_CMD_FRE | PORT_CMD_FR
+    );
+    assert_eq!(cmd & (PORT_CMD_ST | PORT_CMD_CR), 0);
+
+    // 6. Clear PxSERR.
+    write(dev, PORT_BASE + PORT_REG_SERR, u32::MAX);
+    assert_eq!(read(dev, PORT_BASE + PORT_REG_SERR), 0);
+
+    // 7. Clear pending status, enable port interrupts, then enable HBA interrupts.
+    write(dev, PORT_BASE + PORT_REG_IS, u32::MAX);
+    write(dev, HBA_IS, u32::MAX);
+    assert_eq!(read(dev, PORT_BASE + PORT_REG_IS), 0);
+    assert_eq!(read(dev, HBA_IS), 0);
+    write(
+        dev,
+        PORT_BASE + PORT_REG_IE,
+        PORT_IS_DHRS | PORT_IS_PCS | PORT_IS_PRCS,
+    );
+    write(dev, HBA_GHC, GHC_AE | GHC_IE);
+    assert_eq!(read(dev, HBA_GHC), GHC_AE | GHC_IE);
+}
+
+/// AHCI 1.3 §10.3.1: start the command list engine on a ready device.
+fn start_port(dev: &mut AhciPciDevice) {
+    let tfd = read(dev, PORT_BASE + PORT_REG_TFD);
+    assert_eq!(tfd & u32::from(ATA_STATUS_BSY | ATA_STATUS_DRQ), 0);
+    assert_eq!(read(dev, PORT_BASE + PORT_REG_SSTS) & SSTS_DET_MASK, 3);
+
+    write(dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE | PORT_CMD_ST);
+    let cmd = read(dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(cmd & (PORT_CMD_ST | PORT_CMD_CR), PORT_CMD_ST | PORT_CMD_CR);
+}
+
+fn issue_identify(dev: &mut AhciPciDevice, mem: &mut Bus, slot: usize) {
+    let header = CLB + slot as u64 * 32;
+    mem.write_u32(header, 5 | (1 << 16)); // CFL=5 dwords, PRDTL=1
+    mem.write_u32(header + 4, 0);
+    mem.write_u32(header + 8, CTBA as u32);
+    mem.write_u32(header + 12, (CTBA >> 32) as u32);
+
+    let mut cfis = [0u8; 64];
+    cfis[0] = 0x27;
+    cfis[1] = 0x80;
+    cfis[2] = ATA_CMD_IDENTIFY;
+    mem.write_physical(CTBA, &cfis);
+
+    mem.write_u32(CTBA + 0x80, DATA_BUF as u32);
+    mem.write_u32(CTBA + 0x84, 0);
+    mem.write_u32(CTBA + 0x88, 0);
+    mem.write_u32(CTBA + 0x8C, SECTOR_SIZE as u32 - 1);
+
+    write(dev, PORT_BASE + PORT_REG_CI, 1 << slot);
+}
+
+#[test]
+fn init_start_identify_stop_sequence() {
+    let mut dev = device(true);
+    let mut mem = Bus::new(0x20_000);
+
+    init_hba(&mut dev);
+    start_port(&mut dev);
+
+    issue_identify(&mut dev, &mut mem, 3);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CI), 1 << 3);
+    dev.process(&mut mem);
+
+    // Completion: CI cleared, D2H FIS received and mirrored into PxTFD, interrupt raised.
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CI), 0);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_TFD) & 0xFF,
+        u32::from(ATA_STATUS_DRDY | ATA_STATUS_DSC)
+    );
+    assert_eq!(mem.read_u8(FB + 0x40), 0x34);
+    assert_eq!(mem.read_u8(FB + 0x42), ATA_STATUS_DRDY | ATA_STATUS_DSC);
+    assert_ne!(read(&mut dev, PORT_BASE + PORT_REG_IS) & PORT_IS_DHRS, 0);
+    assert_eq!(read(&mut dev, HBA_IS), 1);
+    assert!(dev.intx_level());
+    // PxCMD.CCS reports the slot that was processed last.
+    assert_eq!(
+        (read(&mut dev, PORT_BASE + PORT_REG_CMD) & PORT_CMD_CCS_MASK) >> 8,
+        3
+    );
+    // IDENTIFY word 0 bit 15 clear: ATA device.
+    assert_eq!(mem.read_u16(DATA_BUF) & 0x8000, 0);
+
+    write(&mut dev, PORT_BASE + PORT_REG_IS, PORT_IS_DHRS);
+    write(&mut dev, HBA_IS, 1);
+    assert!(!dev.intx_level());
+
+    // §10.3.2: clear ST and wait for CR, then clear FRE and wait for FR.
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE);
+    let cmd = read(&mut dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(cmd & (PORT_CMD_ST | PORT_CMD_CR | PORT_CMD_CCS_MASK), 0);
+    assert_eq!(cmd & PORT_CMD_FR, PORT_CMD_FR);
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, 0);
+    let cmd = read(&mut dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(cmd & (PORT_CMD_FRE | PORT_CMD_FR), 0);
+}
+
+#[test]
+fn capabilities_advertise_only_implemented_features() {
+    let mut dev = device(false);
+    let cap = read(&mut dev, HBA_CAP);
+    assert_eq!(cap & (CAP_SNCQ | CAP_SSS | CAP_SPM), 0);
+    assert_ne!(cap & (CAP_S64A | CAP_SAM | CAP_SCLO), 0);
+    // Bits 0..4: NP (number of ports minus 1).
+    assert_eq!(cap & 0x1F, 0);
+    assert_eq!(read(&mut dev, HBA_CAP2) & !1, 0);
+
+    // CAP.SAM: GHC.AE is read-only one.
+    write(&mut dev, HBA_GHC, 0);
+    assert_eq!(read(&mut dev, HBA_GHC), GHC_AE);
+
+    // Without staggered spin-up or cold presence detection, SUD and POD read as one.
+    let cmd = read(&mut dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(
+        cmd & (PORT_CMD_SUD | PORT_CMD_POD),
+        PORT_CMD_SUD | PORT_CMD_POD
+    );
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, 0);
+    let cmd = read(&mut dev, PORT_BASE + PORT_REG_CMD);
+    assert_eq!(
+        cmd & (PORT_CMD_SUD | PORT_CMD_POD),
+        PORT_CMD_SUD | PORT_CMD_POD
+    );
+}
+
+#[test]
+fn port_cmd_st_fre_interlocks() {
+    let mut dev = device(true);
+
+    // ST cannot be set without FIS receive enabled.
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_ST);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_CMD) & (PORT_CMD_ST | PORT_CMD_CR),
+        0
+    );
+
+    // ... but once FRE is set, a write of ST alone keeps FRE.
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE);
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_ST);
+    let cmd = read(&mut dev, PORT_BASE + PORT_REG_CMD);
+    let running = PORT_CMD_ST | PORT_CMD_CR | PORT_CMD_FRE | PORT_CMD_FR;
+    assert_eq!(cmd & running, running);
+
+    // CI/SACT are only writable while running; stopping clears them.
+    write(&mut dev, PORT_BASE + PORT_REG_SACT, 0x10);
+    write(&mut dev, PORT_BASE + PORT_REG_SACT, 0x01);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SACT), 0x11);
+    write(&mut dev, PORT_BASE + PORT_REG_CI, 0x01);
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CI), 0);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SACT), 0);
+    write(&mut dev, PORT_BASE + PORT_REG_CI, 0x01);
+    write(&mut dev, PORT_BASE + PORT_REG_SACT, 0x01);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CI), 0);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SACT), 0);
+}
+
+#[test]
+fn command_list_override_clears_busy_and_self_clears() {
+    let mut dev = device(true);
+    write(
+        &mut dev,
+        PORT_BASE + PORT_REG_CMD,
+        PORT_CMD_FRE | PORT_CMD_ST,
+    );
+    // Issue a command without processing it: the task file reports BSY.
+    write(&mut dev, PORT_BASE + PORT_REG_CI, 0x01);
+    assert_ne!(
+        read(&mut dev, PORT_BASE + PORT_REG_TFD) & u32::from(ATA_STATUS_BSY),
+        0
+    );
+
+    write(&mut dev, PORT_BASE + PORT_REG_CMD, PORT_CMD_FRE);
+    write(
+        &mut dev,
+        PORT_BASE + PORT_REG_CMD,
+        PORT_CMD_FRE | PORT_CMD_CLO,
+    );
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_TFD) & u32::from(ATA_STATUS_BSY | ATA_STATUS_DRQ),
+        0
+    );
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CMD) & PORT_CMD_CLO, 0);
+}
+
+#[test]
+fn comreset_posts_signature_fis_and_latches_phy_ready_change() {
+    let mut dev = device(true);
+    let mut mem = Bus::new(0x20_000);
+    init_hba(&mut dev);
+
+    write(&mut dev, PORT_BASE + PORT_REG_SCTL, 1);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SERR), SERR_DIAG_N);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_IS), PORT_IS_PRCS);
+    assert!(dev.intx_level());
+
+    write(&mut dev, PORT_BASE + PORT_REG_SCTL, 0);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SSTS) & SSTS_DET_MASK, 3);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SIG), SATA_SIG_ATA);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_SERR),
+        SERR_DIAG_N | SERR_DIAG_X
+    );
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_IS),
+        PORT_IS_DHRS | PORT_IS_PCS | PORT_IS_PRCS
+    );
+
+    // The signature FIS lands in the receive area once the controller runs.
+    dev.process(&mut mem);
+    let mut fis = [0u8; 20];
+    mem.read_physical(FB + 0x40, &mut fis);
+    assert_eq!(fis[0], 0x34);
+    assert_eq!(fis[2], ATA_STATUS_DRDY | ATA_STATUS_DSC);
+    assert_eq!(fis[3], 0x01);
+    assert_eq!((fis[4], fis[5], fis[6], fis[12]), (0x01, 0x00, 0x00, 0x01));
+
+    // PRCS/PCS are cleared through PxSERR, not PxIS.
+    write(&mut dev, PORT_BASE + PORT_REG_IS, u32::MAX);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_IS),
+        PORT_IS_PCS | PORT_IS_PRCS
+    );
+    write(
+        &mut dev,
+        PORT_BASE + PORT_REG_SERR,
+        SERR_DIAG_N | SERR_DIAG_X,
+    );
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_IS), 0);
+    write(&mut dev, HBA_IS, u32::MAX);
+    assert!(!dev.intx_level());
+}
+
+#[test]
+fn hot_attach_and_detach_raise_phy_ready_change_interrupts() {
+    let mut dev = device(false);
+    let mut mem = Bus::new(0x20_000);
+    init_hba(&mut dev);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SSTS) & SSTS_DET_MASK, 0);
+    assert!(!dev.intx_level());
+
+    dev.hot_attach_drive(0, drive());
+    assert!(dev.intx_level());
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SSTS) & SSTS_DET_MASK, 3);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SIG), SATA_SIG_ATA);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_IS),
+        PORT_IS_DHRS | PORT_IS_PCS | PORT_IS_PRCS
+    );
+    dev.process(&mut mem);
+    assert_eq!(mem.read_u8(FB + 0x40), 0x34);
+
+    write(&mut dev, PORT_BASE + PORT_REG_IS, u32::MAX);
+    write(&mut dev, PORT_BASE + PORT_REG_SERR, u32::MAX);
+    write(&mut dev, HBA_IS, u32::MAX);
+    assert!(!dev.intx_level());
+
+    // The attached device is usable without further link management.
+    start_port(&mut dev);
+    issue_identify(&mut dev, &mut mem, 0);
+    dev.process(&mut mem);
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_CI), 0);
+
+    write(&mut dev, PORT_BASE + PORT_REG_IS, u32::MAX);
+    write(&mut dev, HBA_IS, u32::MAX);
+    dev.hot_detach_drive(0);
+    assert!(dev.intx_level());
+    assert_eq!(read(&mut dev, PORT_BASE + PORT_REG_SSTS) & SSTS_DET_MASK, 0);
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_SERR),
+        SERR_DIAG_N | SERR_DIAG_X
+    );
+    assert_eq!(
+        read(&mut dev, PORT_BASE + PORT_REG_IS),
+        PORT_IS_PCS | PORT_IS_PRCS
+    );
+    assert!(!dev.drive_attached(0));
+}
@@ -0,0 +1,202 @@
+use aero_devices::dma::Dma8237;
+use aero_devices::irq::IrqLine;
+use aero_devices_storage::fdc::{FloppyController, FLOPPY_144_TOTAL_SECTORS};
+use aero_io_snapshot::io::state::IoSnapshot;
+use aero_storage::{MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
+use memory::{Bus, MemoryBus};
+use std::cell::Cell;
+use std::rc::Rc;
+
+const DOR: u16 = 2;
+const MSR: u16 = 4;
+const FIFO: u16 = 5;
+const DIR: u16 = 7;
+
+#[derive(Clone, Default)]
+struct TestIrq(Rc<Cell<bool>>);
+
+impl IrqLine for TestIrq {
+    fn set_level(&self, level: bool) {
+        self.0.set(level);
+    }
+}
+
+fn floppy_image() -> RawDisk<MemBackend> {
+    let mut disk = RawDisk::create(
+        MemBackend::new(),
+        FLOPPY_144_TOTAL_SECTORS * SECTOR_SIZE as u64,
+    )
+    .unwrap();
+    for lba in 0..FLOPPY_144_TOTAL_SECTORS {
+        let sector = [(lba % 251) as u8; SECTOR_SIZE];
+        disk.write_sectors(lba, &sector).unwrap();
+    }
+    disk
+}
+
+fn command(fdc: &mut FloppyController, bytes: &[u8]) {
+    for &b in bytes {
+        assert_eq!(
+            fdc.read_u8(MSR) & 0xC0,
+            0x80,
+            "FDC not ready for command byte"
+        );
+        fdc.write_u8(FIFO, b);
+    }
+}
+
+fn results(fdc: &mut FloppyController) -> Vec<u8> {
+    let mut out = Vec::new();
+    while fdc.read_u8(MSR) & 0xC0 == 0xC0 {
+        out.push(fdc.read_u8(FIFO));
+    }
+    out
+}
+
+/// Program DMA channel 2 for a single-mode transfer of `len` bytes at `addr`.
+fn program_dma(dma: &mut Dma8237, addr: u32, len: u16, to_memory: bool) {
+    dma.write_u8(0x0A, 0x06); // mask channel 2
+    dma.write_u8(0x0C, 0);
+    dma.write_u8(0x0B, if to_memory { 0x46 } else { 0x4A });
+    dma.write_u8(0x04, addr as u8);
+    dma.write_u8(0x04, (addr >> 8) as u8);
+    dma.write_u8(0x81, (addr >> 16) as u8);
+    dma.write_u8(0x05, (len - 1) as u8);
+    dma.write_u8(0x05, ((len - 1) >> 8) as u8);
+    dma.write_u8(0x0A, 0x02); // unmask channel 2
+}
+
+fn reset_controller(fdc: &mut FloppyController, irq: &TestIrq) {
+    fdc.write_u8(DOR, 0x00);
+    fdc.write_u8(DOR, 0x1C); // motor A on, DMA/IRQ enabled, out of reset
+    assert!(irq.0.get(), "reset should raise IRQ6");
+    for drive in 0..4u8 {
+        command(fdc, &[0x08]);
+        assert_eq!(results(fdc), vec![0xC0 | drive, 0]);
+    }
+    assert!(!irq.0.get());
+}
+
+#[test]
+fn fdc_reset_seek_and_dma_read() {
+    let irq = TestIrq::default();
+    let mut fdc = FloppyController::new(Box::new(irq.clone()));
+    fdc.attach_media(0, Box::new(floppy_image()));
+    let mut dma = Dma8237::new();
+    let mut mem = Bus::new(0x40_000);
+
+    reset_controller(&mut fdc, &irq);
+
+    command(&mut fdc, &[0x10]);
+    assert_eq!(results(&mut fdc), vec![0x90]);
+
+    // Media was just inserted: the disk-change line stays high until the heads step.
+    assert_eq!(fdc.read_u8(DIR) & 0x80, 0x80);
+    command(&mut fdc, &[0x07, 0x00]);
+    assert!(irq.0.get());
+    command(&mut fdc, &[0x08]);
+    assert_eq!(results(&mut fdc), vec![0x20, 0]);
+    assert!(!irq.0.get());
+    assert_eq!(fdc.read_u8(DIR) & 0x80, 0);
+
+    command(&mut fdc, &[0x0F, 0x04, 5]);
+    command(&mut fdc, &[0x08]);
+    assert_eq!(results(&mut fdc), vec![0x24, 5]);
+
+    // Read C=5 H=1 R=17..18 (multi-track off): LBA = (5*2+1)*18 + 16 = 214.
+    program_dma(&mut dma, 0x1_0000, 2 * SECTOR_SIZE as u16, true);
+    command(&mut fdc, &[0x46, 0x04, 5, 1, 17, 2, 18, 0x1B, 0xFF]);
+    assert!(fdc.transfer_pending());
+    assert!(!irq.0.get());
+
+    fdc.tick(&mut dma, &mut mem);
+    assert!(irq.0.get());
+    assert_eq!(results(&mut fdc), vec![0x04, 0, 0, 6, 1, 1, 2]);
+    assert!(!irq.0.get());
+
+    let mut buf = vec![0u8; 2 * SECTOR_SIZE];
+    mem.read_physical(0x1_0000, &mut buf);
+    assert!(buf[..SECTOR_SIZE].iter().all(|&b| b == 214u8));
+    assert!(buf[SECTOR_SIZE..].iter().all(|&b| b == 215u8));
+}
+
+#[test]
+fn fdc_write_and_format_reach_media() {
+    let irq = TestIrq::default();
+    let mut fdc = FloppyController::new(Box::new(irq.clone()));
+    fdc.attach_media(0, Box::new(floppy_image()));
+    let mut dma = Dma8237::new();
+    let mut mem = Bus::new(0x40_000);
+    reset_controller(&mut fdc, &irq);
+
+    // WRITE DATA C=0 H=0 R=3.
+    mem.write_physical(0x2000, &[0xA5; SECTOR_SIZE]);
+    program_dma(&mut dma, 0x2000, SECTOR_SIZE as u16, false);
+    command(&mut fdc, &[0x45, 0x00, 0, 0, 3, 2, 18, 0x1B, 0xFF]);
+    fdc.tick(&mut dma, &mut mem);
+    assert_eq!(results(&mut fdc), vec![0x00, 0, 0, 0, 0, 4, 2]);
+
+    // FORMAT TRACK C=0 H=1 with filler 0xF6 (18 four-byte sector IDs supplied over DMA).
+    let ids: Vec<u8> = (1..=18u8).flat_map(|r| [0, 1, r, 2]).collect();
+    mem.write_physical(0x3000, &ids);
+    program_dma(&mut dma, 0x3000, ids.len() as u16, false);
+    command(&mut fdc, &[0x4D, 0x04, 2, 18, 0x54, 0xF6]);
+    fdc.tick(&mut dma, &mut mem);
+    assert_eq!(results(&mut fdc), vec![0x04, 0, 0, 0, 1, 18, 2]);
+
+    let mut disk = fdc.eject_media(0).unwrap();
+    let mut sector = [0u8; SECTOR_SIZE];
+    disk.read_sectors(2, &mut sector).unwrap();
+    assert_eq!(sector, [0xA5; SECTOR_SIZE]);
+    for lba in 18..36 {
+        disk.read_sectors(lba, &mut sector).unwrap();
+        assert_eq!(sector, [0xF6; SECTOR_SIZE]);
+    }
+    disk.read_sectors(36, &mut sector).unwrap();
+    assert_eq!(sector, [36; SECTOR_SIZE]);
+}
+
+#[test]
+fn fdc_read_without_media_and_invalid_commands_report_errors() {
+    let irq = TestIrq::default();
+    let mut fdc = FloppyController::new(Box::new(irq.clone()));
+    let mut dma = Dma8237::new();
+    let mut mem = Bus::new(0x40_000);
+    reset_controller(&mut fdc, &irq);
+
+    command(&mut fdc, &[0x1F]);
+    assert_eq!(results(&mut fdc), vec![0x80]);
+
+    // SENSE INTERRUPT without a pending interrupt is rejected the same way.
+    command(&mut fdc, &[0x08]);
+    assert_eq!(results(&mut fdc), vec![0x80]);
+
+    program_dma(&mut dma, 0x1000, SECTOR_SIZE as u16, true);
+    command(&mut fdc, &[0x46, 0x00, 0, 0, 1, 2, 18, 0x1B, 0xFF]);
+    fdc.tick(&mut dma, &mut mem);
+    assert_eq!(results(&mut fdc), vec![0x40, 0x01, 0, 0, 0, 1, 2]);
+}
+
+#[test]
+fn fdc_snapshot_restores_controller_state_but_keeps_host_media() {
+    let irq = TestIrq::default();
+    let mut fdc = FloppyController::new(Box::new(irq.clone()));
+    fdc.attach_media(0, Box::new(floppy_image()));
+    reset_controller(&mut fdc, &irq);
+
+    command(&mut fdc, &[0x0F, 0x00, 40]);
+    assert!(irq.0.get());
+    let snap = fdc.save_state();
+
+    // Restoring into a controller with its own media keeps that media attached.
+    let restored_irq = TestIrq::default();
+    let mut restored = FloppyController::new(Box::new(restored_irq.clone()));
+    restored.attach_media(0, Box::new(floppy_image()));
+    restored.load_state(&snap).unwrap();
+    assert!(restored_irq.0.get());
+    assert!(restored.has_media(0));
+
+    command(&mut restored, &[0x08]);
+    assert_eq!(results(&mut restored), vec![0x20, 40]);
+    assert!(!restored_irq.0.get());
+}
@@ -1,11 +1,11 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 use aero_devices::pci::profile;
 use aero_devices::pci::PciDevice as _;
 use aero_devices_storage::ata::{
-    AtaDrive, ATA_CMD_IDENTIFY, ATA_CMD_READ_DMA_EXT, ATA_STATUS_BSY, ATA_STATUS_DRDY,
-    ATA_STATUS_DSC,
+    AtaDrive, ATA_CMD_FLUSH_CACHE_EXT, ATA_CMD_IDENTIFY, ATA_CMD_READ_DMA_EXT,
+    ATA_CMD_WRITE_DMA_EXT, ATA_STATUS_BSY, ATA_STATUS_DRDY, ATA_STATUS_DSC,
 };
 use aero_devices_storage::AhciPciDevice;
 use aero_storage::{MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
@@ -69,6 +69,37 @@ impl VirtualDisk for DropDetectDisk {
     }
 }
 
+/// Disk with ordered writes that counts barriers and flushes separately.
+struct CountingDisk {
+    inner: RawDisk<MemBackend>,
+    barriers: Arc<AtomicU32>,
+    flushes: Arc<AtomicU32>,
+}
+
+impl VirtualDisk for CountingDisk {
+    fn capacity_bytes(&self) -> u64 {
+        self.inner.capacity_bytes()
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> aero_storage::Result<()> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> aero_storage::Result<()> {
+        self.inner.write_at(offset, buf)
+    }
+
+    fn flush(&mut self) -> aero_storage::Result<()> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        self.inner.flush()
+    }
+
+    fn write_barrier(&mut self) -> aero_storage::Result<()> {
+        self.barriers.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
 fn write_cmd_header(
     mem: &mut dyn MemoryBus,
     clb: u64,
@@ -634,3 +665,75 @@ fn w1c_px_is_byte_write_does_not_clear_other_status_bits() {
     // TFES is still enabled and pending, so INTx should remain asserted.
     assert!(dev.intx_level());
 }
+
+#[test]
+fn write_flush_write_flush_in_one_batch_costs_one_backend_flush() {
+    let barriers = Arc::new(AtomicU32::new(0));
+    let flushes = Arc::new(AtomicU32::new(0));
+    let disk = CountingDisk {
+        inner: RawDisk::create(MemBackend::new(), 8 * SECTOR_SIZE as u64).unwrap(),
+        barriers: barriers.clone(),
+        flushes: flushes.clone(),
+    };
+
+    let mut dev = AhciPciDevice::new(1);
+    dev.attach_drive(0, AtaDrive::new(Box::new(disk)).unwrap());
+    dev.config_mut().set_command(0x0006); // MEM + BUSMASTER
+
+    let mut mem = Bus::new(0x20_000);
+    let clb = 0x1000u64;
+    let fb = 0x2000u64;
+    let data = [0x8000u64, 0x9000];
+
+    dev.mmio_write(PORT_BASE + PORT_REG_CLB, 4, clb);
+    dev.mmio_write(PORT_BASE + PORT_REG_CLBU, 4, clb >> 32);
+    dev.mmio_write(PORT_BASE + PORT_REG_FB, 4, fb);
+    dev.mmio_write(PORT_BASE + PORT_REG_FBU, 4, fb >> 32);
+    dev.mmio_write(HBA_GHC, 4, u64::from(GHC_AE));
+    dev.mmio_write(
+        PORT_BASE + PORT_REG_CMD,
+        4,
+        u64::from(PORT_CMD_ST | PORT_CMD_FRE),
+    );
+
+    // Slots 0..3: WRITE DMA EXT LBA 0, FLUSH CACHE EXT, WRITE DMA EXT LBA 1, FLUSH CACHE EXT.
+    mem.write_physical(data[0], &[0xAA; SECTOR_SIZE]);
+    mem.write_physical(data[1], &[0xBB; SECTOR_SIZE]);
+    for slot in 0..4usize {
+        let ctba = 0x3000 + slot as u64 * 0x100;
+        if slot % 2 == 0 {
+            write_cmd_header(&mut mem, clb, slot, ctba, 1, true);
+            write_cfis(&mut mem, ctba, ATA_CMD_WRITE_DMA_EXT, (slot / 2) as u64, 1);
+            write_prdt(&mut mem, ctba, 0, data[slot / 2], SECTOR_SIZE as u32);
+        } else {
+            write_cmd_header(&mut mem, clb, slot, ctba, 0, false);
+            write_cfis(&mut mem, ctba, ATA_CMD_FLUSH_CACHE_EXT, 0, 0);
+        }
+    }
+
+    dev.mmio_write(PORT_BASE + PORT_REG_CI, 4, 0xF);
+    dev.process(&mut mem);
+
+    assert_eq!(dev.mmio_read(PORT_BASE + PORT_REG_CI, 4) as u32, 0);
+    assert_eq!(
+        dev.mmio_read(PORT_BASE + PORT_REG_IS, 4) as u32 & PORT_IS_TFES,
+        0
+    );
+    // The first FLUSH only has to order the second write behind the first; the single flush at
+    // the end of the batch completes both FLUSH commands.
+    assert_eq!(barriers.load(Ordering::SeqCst), 1);
+    assert_eq!(flushes.load(Ordering::SeqCst), 1);
+
+    // Read the data back through the controller.
+    let read_buf = 0xA000u64;
+    let ctba = 0x3000u64;
+    write_cmd_header(&mut mem, clb, 0, ctba, 1, false);
+    write_cfis(&mut mem, ctba, ATA_CMD_READ_DMA_EXT, 1, 1);
+    write_prdt(&mut mem, ctba, 0, read_buf, SECTOR_SIZE as u32);
+    dev.mmio_write(PORT_BASE + PORT_REG_CI, 4, 1);
+    dev.process(&mut mem);
+
+    let mut out = [0u8; SECTOR_SIZE];
+    mem.read_physical(read_buf, &mut out);
+    assert!(out.iter().all(|b| *b == 0xBB));
+}
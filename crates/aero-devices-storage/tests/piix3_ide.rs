@@ -85,11 +85,11 @@ impl Drop for DropDetectIso {
 }
 
 impl IsoBackend for DropDetectIso {
-    fn sector_count(&self) -> u32 {
+    fn sector_count(&self) -> u64 {
         self.inner.sector_count()
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         self.inner.read_sectors(lba, buf)
     }
 }
@@ -3321,11 +3321,11 @@ impl MemIso {
 }
 
 impl IsoBackend for MemIso {
-    fn sector_count(&self) -> u32 {
-        self.sector_count
+    fn sector_count(&self) -> u64 {
+        u64::from(self.sector_count)
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         if !buf.len().is_multiple_of(2048) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -3508,11 +3508,11 @@ fn atapi_read_12_rejects_oversized_transfer_without_allocating_buffer() {
     }
 
     impl IsoBackend for ZeroIso {
-        fn sector_count(&self) -> u32 {
-            self.sector_count
+        fn sector_count(&self) -> u64 {
+            u64::from(self.sector_count)
         }
 
-        fn read_sectors(&mut self, _lba: u32, buf: &mut [u8]) -> io::Result<()> {
+        fn read_sectors(&mut self, _lba: u64, buf: &mut [u8]) -> io::Result<()> {
             if !buf.len().is_multiple_of(2048) {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_devices::pci::PciDevice;
+use aero_devices_storage::ata::AtaDrive;
+use aero_devices_storage::pci_ide::{register_piix3_ide_ports, Piix3IdePciDevice, PRIMARY_PORTS};
+use aero_platform::io::IoPortBus;
+use aero_storage::{MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
+use memory::{Bus, MemoryBus};
+
+const DISK_SECTORS: u64 = 256;
+const PRD_ADDR: u64 = 0x1000;
+
+const BM_ACTIVE: u8 = 0x01;
+const BM_ERROR: u8 = 0x02;
+const BM_INTERRUPT: u8 = 0x04;
+
+const ATA_READ_DMA: u8 = 0xC8;
+const ATA_WRITE_DMA: u8 = 0xCA;
+const ATA_STATUS_ERR: u8 = 0x01;
+
+fn disk_byte(offset: usize) -> u8 {
+    (offset as u8).wrapping_mul(7) ^ (offset / SECTOR_SIZE) as u8
+}
+
+struct Rig {
+    ide: Rc<RefCell<Piix3IdePciDevice>>,
+    io: IoPortBus,
+    mem: Bus,
+    bm_base: u16,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let mut disk =
+            RawDisk::create(MemBackend::new(), DISK_SECTORS * SECTOR_SIZE as u64).unwrap();
+        let pattern: Vec<u8> = (0..DISK_SECTORS as usize * SECTOR_SIZE)
+            .map(disk_byte)
+            .collect();
+        disk.write_sectors(0, &pattern).unwrap();
+
+        let ide = Rc::new(RefCell::new(Piix3IdePciDevice::new()));
+        ide.borrow_mut()
+            .controller
+            .attach_primary_master_ata(AtaDrive::new(Box::new(disk)).unwrap());
+        ide.borrow_mut().config_mut().set_command(0x0005); // IO decode + Bus Master
+
+        let mut io = IoPortBus::new();
+        register_piix3_ide_ports(&mut io, ide.clone());
+        let bm_base = ide.borrow().bus_master_base();
+        Self {
+            ide,
+            io,
+            mem: Bus::new(0x40_000),
+            bm_base,
+        }
+    }
+
+    /// Write `(addr, byte_count, eot)` entries at [`PRD_ADDR`] and point the engine at them.
+    fn set_prds(&mut self, prds: &[(u64, u16, bool)]) {
+        for (i, &(addr, byte_count, eot)) in prds.iter().enumerate() {
+            let entry = PRD_ADDR + 8 * i as u64;
+            self.mem.write_u32(entry, addr as u32);
+            self.mem.write_u16(entry + 4, byte_count);
+            self.mem.write_u16(entry + 6, if eot { 0x8000 } else { 0 });
+        }
+        self.io.write(self.bm_base + 4, 4, PRD_ADDR as u32);
+    }
+
+    fn issue(&mut self, command: u8, lba: u32, sectors: u8) {
+        let cmd_base = PRIMARY_PORTS.cmd_base;
+        self.io.write(cmd_base + 6, 1, 0xE0 | ((lba >> 24) & 0x0F));
+        self.io.write(cmd_base + 2, 1, u32::from(sectors));
+        self.io.write(cmd_base + 3, 1, lba & 0xFF);
+        self.io.write(cmd_base + 4, 1, (lba >> 8) & 0xFF);
+        self.io.write(cmd_base + 5, 1, (lba >> 16) & 0xFF);
+        self.io.write(cmd_base + 7, 1, u32::from(command));
+    }
+
+    fn bm_status(&mut self) -> u8 {
+        self.io.read(self.bm_base + 2, 1) as u8 & 0x07
+    }
+
+    /// Start the engine and run the transfer, returning the BMIDE status before and after.
+    fn run(&mut self, to_memory: bool) -> (u8, u8) {
+        self.io
+            .write(self.bm_base, 1, if to_memory { 0x09 } else { 0x01 });
+        let before = self.bm_status();
+        self.ide.borrow_mut().tick(&mut self.mem);
+        (before, self.bm_status())
+    }
+
+    /// What a driver does in its interrupt handler: stop the engine, ack BMIDE, read ATA status.
+    fn complete(&mut self) -> u8 {
+        self.io.write(self.bm_base, 1, 0x00);
+        let stopped = self.bm_status();
+        self.io
+            .write(self.bm_base + 2, 1, u32::from(BM_INTERRUPT | BM_ERROR));
+        assert_eq!(self.bm_status(), 0);
+        let ata_status = self.io.read(PRIMARY_PORTS.cmd_base + 7, 1) as u8;
+        assert!(!self.ide.borrow().controller.primary_irq_pending());
+        assert_eq!(stopped & BM_ACTIVE, 0, "clearing START must clear ACTIVE");
+        ata_status
+    }
+
+    fn read_mem(&mut self, addr: u64, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        self.mem.read_physical(addr, &mut buf);
+        buf
+    }
+
+    /// Read sectors back from the disk through a plain single-entry DMA.
+    fn read_back(&mut self, lba: u32, sectors: u8) -> Vec<u8> {
+        let len = usize::from(sectors) * SECTOR_SIZE;
+        self.set_prds(&[(0x3_0000, len as u16, true)]);
+        self.issue(ATA_READ_DMA, lba, sectors);
+        assert_eq!(self.run(true).1, BM_INTERRUPT);
+        self.complete();
+        self.read_mem(0x3_0000, len)
+    }
+}
+
+fn disk_bytes(lba: usize, len: usize) -> Vec<u8> {
+    let start = lba * SECTOR_SIZE;
+    (start..start + len).map(disk_byte).collect()
+}
+
+#[test]
+fn zero_byte_count_is_64kib_and_entries_may_end_on_a_64kib_boundary() {
+    let mut rig = Rig::new();
+    // The first entry covers [0x1_0000, 0x2_0000) and ends exactly on the 64KiB boundary.
+    rig.set_prds(&[(0x1_0000, 0, false), (0x2_0000, SECTOR_SIZE as u16, true)]);
+    rig.mem
+        .write_physical(0x2_0000 + SECTOR_SIZE as u64, &[0xEE; 16]);
+
+    rig.issue(ATA_READ_DMA, 0, 129);
+    assert_eq!(rig.bm_status(), 0);
+    assert_eq!(rig.run(true), (0, BM_INTERRUPT));
+    assert!(rig.ide.borrow().controller.primary_irq_pending());
+
+    assert_eq!(rig.read_mem(0x1_0000, 0x1_0000), disk_bytes(0, 0x1_0000));
+    assert_eq!(
+        rig.read_mem(0x2_0000, SECTOR_SIZE),
+        disk_bytes(128, SECTOR_SIZE)
+    );
+    assert_eq!(rig.read_mem(0x2_0000 + SECTOR_SIZE as u64, 16), [0xEE; 16]);
+    assert_eq!(rig.complete() & ATA_STATUS_ERR, 0);
+}
+
+#[test]
+fn table_larger_than_the_command_stops_at_the_sector_count_with_active_still_set() {
+    let mut rig = Rig::new();
+    // 512 + 64KiB described; the command only moves two sectors.
+    rig.set_prds(&[(0x1_0000, SECTOR_SIZE as u16, false), (0x2_0000, 0, true)]);
+    rig.mem.write_physical(0x2_0000, &[0xEE; 2 * SECTOR_SIZE]);
+
+    rig.issue(ATA_READ_DMA, 4, 2);
+    // Per the PIIX spec a table that outlasts the transfer reports ACTIVE and INTERRUPT together.
+    assert_eq!(rig.run(true), (0, BM_ACTIVE | BM_INTERRUPT));
+    assert!(rig.ide.borrow().controller.primary_irq_pending());
+
+    assert_eq!(
+        rig.read_mem(0x1_0000, SECTOR_SIZE),
+        disk_bytes(4, SECTOR_SIZE)
+    );
+    assert_eq!(
+        rig.read_mem(0x2_0000, SECTOR_SIZE),
+        disk_bytes(5, SECTOR_SIZE)
+    );
+    assert_eq!(
+        rig.read_mem(0x2_0000 + SECTOR_SIZE as u64, SECTOR_SIZE),
+        vec![0xEE; SECTOR_SIZE]
+    );
+    assert_eq!(rig.complete() & ATA_STATUS_ERR, 0);
+
+    // An unterminated entry that extends past the data is still a larger-than-transfer table.
+    rig.set_prds(&[(0x1_0000, 0, false)]);
+    rig.issue(ATA_READ_DMA, 8, 1);
+    assert_eq!(rig.run(true), (0, BM_ACTIVE | BM_INTERRUPT));
+    assert_eq!(
+        rig.read_mem(0x1_0000, SECTOR_SIZE),
+        disk_bytes(8, SECTOR_SIZE)
+    );
+    assert_eq!(rig.complete() & ATA_STATUS_ERR, 0);
+}
+
+#[test]
+fn writes_gather_entries_that_split_sectors() {
+    let mut rig = Rig::new();
+    let data: Vec<u8> = (0..2 * SECTOR_SIZE).map(|i| (i % 251) as u8).collect();
+    // Entry boundaries at 300 and 900 bytes: both land in the middle of a sector.
+    rig.mem.write_physical(0x1_0000, &data[..300]);
+    rig.mem.write_physical(0x1_8000, &data[300..900]);
+    rig.mem.write_physical(0x2_0000, &data[900..]);
+    rig.set_prds(&[
+        (0x1_0000, 300, false),
+        (0x1_8000, 600, false),
+        (0x2_0000, (2 * SECTOR_SIZE - 900) as u16, true),
+    ]);
+
+    rig.issue(ATA_WRITE_DMA, 10, 2);
+    assert_eq!(rig.run(false), (0, BM_INTERRUPT));
+    assert_eq!(rig.complete() & ATA_STATUS_ERR, 0);
+
+    assert_eq!(rig.read_back(10, 2), data);
+    assert_eq!(rig.read_back(12, 1), disk_bytes(12, SECTOR_SIZE));
+}
+
+#[test]
+fn table_exhausted_before_the_sector_count_sets_error() {
+    let mut rig = Rig::new();
+    // 300 + 100 bytes for a one-sector write: EOT arrives mid-sector.
+    rig.mem.write_physical(0x1_0000, &[0xA5; 400]);
+    rig.set_prds(&[(0x1_0000, 300, false), (0x1_8000, 100, true)]);
+
+    rig.issue(ATA_WRITE_DMA, 20, 1);
+    assert_eq!(rig.run(false), (0, BM_ERROR | BM_INTERRUPT));
+    assert!(rig.ide.borrow().controller.primary_irq_pending());
+    assert_ne!(rig.complete() & ATA_STATUS_ERR, 0);
+    // The partially gathered sector is never committed.
+    assert_eq!(rig.read_back(20, 1), disk_bytes(20, SECTOR_SIZE));
+
+    // Reads move what the table describes before reporting the same error.
+    rig.mem.write_physical(0x1_0000, &[0xEE; SECTOR_SIZE]);
+    rig.set_prds(&[(0x1_0000, 256, true), (0x1_8000, 256, true)]);
+    rig.issue(ATA_READ_DMA, 21, 1);
+    assert_eq!(rig.run(true), (0, BM_ERROR | BM_INTERRUPT));
+    assert_eq!(rig.read_mem(0x1_0000, 256), disk_bytes(21, 256));
+    assert_eq!(rig.read_mem(0x1_0000 + 256, 256), vec![0xEE; 256]);
+    assert_ne!(rig.complete() & ATA_STATUS_ERR, 0);
+}
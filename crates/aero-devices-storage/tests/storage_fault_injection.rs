@@ -0,0 +1,214 @@
+use aero_devices::pci::PciDevice as _;
+use aero_devices_storage::ata::{AtaDrive, ATA_CMD_READ_DMA_EXT, ATA_STATUS_BSY};
+use aero_devices_storage::pci_ide::{Piix3IdePciDevice, PRIMARY_PORTS};
+use aero_devices_storage::AhciPciDevice;
+use aero_storage::{
+    MemBackend, RawDisk, StorageFaultDelay, StorageFaultError, StorageFaultInjector,
+    StorageFaultPolicy, VirtualDisk, SECTOR_SIZE,
+};
+use memory::{Bus, MemoryBus};
+
+const DELAY_NS: u64 = 5_000_000;
+
+const HBA_GHC: u64 = 0x04;
+const PORT_BASE: u64 = 0x100;
+const PORT_REG_CLB: u64 = 0x00;
+const PORT_REG_FB: u64 = 0x08;
+const PORT_REG_IS: u64 = 0x10;
+const PORT_REG_IE: u64 = 0x14;
+const PORT_REG_CMD: u64 = 0x18;
+const PORT_REG_TFD: u64 = 0x20;
+const PORT_REG_CI: u64 = 0x38;
+
+const GHC_IE: u64 = 1 << 1;
+const GHC_AE: u64 = 1 << 31;
+const PORT_CMD_ST: u64 = 1 << 0;
+const PORT_CMD_FRE: u64 = 1 << 4;
+const PORT_IS_DHRS: u64 = 1 << 0;
+
+const CLB: u64 = 0x1000;
+const FB: u64 = 0x2000;
+const CTBA: u64 = 0x3000;
+const READ_BUF: u64 = 0x8000;
+
+fn delay_policy() -> StorageFaultPolicy {
+    StorageFaultPolicy {
+        delay: StorageFaultDelay::Fixed { ns: DELAY_NS },
+        ..Default::default()
+    }
+}
+
+/// A disk whose sector `n` is filled with byte `n`.
+fn patterned_disk() -> RawDisk<MemBackend> {
+    let mut disk = RawDisk::create(MemBackend::new(), 16 * SECTOR_SIZE as u64).unwrap();
+    for lba in 0..16u8 {
+        disk.write_sectors(u64::from(lba), &[lba; SECTOR_SIZE])
+            .unwrap();
+    }
+    disk
+}
+
+fn ahci_with_disk() -> (AhciPciDevice, Bus) {
+    let mut dev = AhciPciDevice::new(1);
+    dev.attach_drive(0, AtaDrive::new(Box::new(patterned_disk())).unwrap());
+    dev.config_mut().set_command(0x0006); // MEM + BUSMASTER
+
+    dev.mmio_write(PORT_BASE + PORT_REG_CLB, 4, CLB);
+    dev.mmio_write(PORT_BASE + PORT_REG_FB, 4, FB);
+    dev.mmio_write(HBA_GHC, 4, GHC_IE | GHC_AE);
+    dev.mmio_write(PORT_BASE + PORT_REG_IE, 4, PORT_IS_DHRS);
+    dev.mmio_write(PORT_BASE + PORT_REG_CMD, 4, PORT_CMD_ST | PORT_CMD_FRE);
+    (dev, Bus::new(0x20_000))
+}
+
+/// Builds a one-sector READ DMA EXT of LBA `slot` in command slot `slot`.
+fn queue_ahci_read(mem: &mut Bus, slot: u64) {
+    let ctba = CTBA + slot * 0x100;
+    let header = CLB + slot * 32;
+    mem.write_u32(header, 5 | (1 << 16)); // CFL=5 dwords, PRDTL=1
+    mem.write_u32(header + 4, 0);
+    mem.write_u32(header + 8, ctba as u32);
+    mem.write_u32(header + 12, 0);
+
+    let mut cfis = [0u8; 64];
+    cfis[0] = 0x27;
+    cfis[1] = 0x80;
+    cfis[2] = ATA_CMD_READ_DMA_EXT;
+    cfis[4] = slot as u8;
+    cfis[7] = 0x40;
+    cfis[12] = 1;
+    mem.write_physical(ctba, &cfis);
+
+    let prd = ctba + 0x80;
+    mem.write_u32(prd, (READ_BUF + slot * SECTOR_SIZE as u64) as u32);
+    mem.write_u32(prd + 4, 0);
+    mem.write_u32(prd + 8, 0);
+    mem.write_u32(prd + 12, SECTOR_SIZE as u32 - 1);
+}
+
+#[test]
+fn ahci_delayed_slots_are_outstanding_concurrently() {
+    let (mut dev, mut mem) = ahci_with_disk();
+    dev.set_fault_injector(Some(StorageFaultInjector::new(delay_policy())));
+
+    for slot in 0..4 {
+        queue_ahci_read(&mut mem, slot);
+    }
+    dev.mmio_write(PORT_BASE + PORT_REG_CI, 4, 0xF);
+    dev.process(&mut mem);
+
+    // Every slot was issued, none has completed.
+    assert_eq!(dev.deferred_command_count(), 4);
+    assert_eq!(dev.mmio_read(PORT_BASE + PORT_REG_CI, 4), 0xF);
+    assert_ne!(
+        dev.mmio_read(PORT_BASE + PORT_REG_TFD, 4) & u64::from(ATA_STATUS_BSY),
+        0
+    );
+    assert_eq!(dev.mmio_read(PORT_BASE + PORT_REG_IS, 4) & PORT_IS_DHRS, 0);
+    assert!(!dev.intx_level());
+
+    // Processing again before the due time must not re-issue the deferred slots.
+    dev.set_time_ns(DELAY_NS / 2);
+    dev.process(&mut mem);
+    assert_eq!(dev.deferred_command_count(), 4);
+    assert_eq!(
+        dev.fault_injector().unwrap().counters().requests,
+        4,
+        "deferred slots were re-issued"
+    );
+
+    dev.set_time_ns(DELAY_NS);
+    dev.process(&mut mem);
+    assert_eq!(dev.deferred_command_count(), 0);
+    assert_eq!(dev.mmio_read(PORT_BASE + PORT_REG_CI, 4), 0);
+    assert_ne!(dev.mmio_read(PORT_BASE + PORT_REG_IS, 4) & PORT_IS_DHRS, 0);
+    assert!(dev.intx_level());
+    for slot in 0..4u64 {
+        let mut sector = [0u8; SECTOR_SIZE];
+        mem.read_physical(READ_BUF + slot * SECTOR_SIZE as u64, &mut sector);
+        assert_eq!(sector, [slot as u8; SECTOR_SIZE]);
+    }
+}
+
+#[test]
+fn ahci_lba_range_failure_sets_task_file_error() {
+    let (mut dev, mut mem) = ahci_with_disk();
+    dev.set_fault_injector(Some(StorageFaultInjector::new(StorageFaultPolicy {
+        fail_lba_range: Some((1..2, StorageFaultError::Io)),
+        ..Default::default()
+    })));
+
+    queue_ahci_read(&mut mem, 0);
+    queue_ahci_read(&mut mem, 1);
+    dev.mmio_write(PORT_BASE + PORT_REG_CI, 4, 0x3);
+    dev.process(&mut mem);
+
+    let tfd = dev.mmio_read(PORT_BASE + PORT_REG_TFD, 4);
+    assert_ne!(tfd & 0x01, 0, "ERR should be set");
+    assert_eq!((tfd >> 8) & 0xFF, 0x04, "ABRT");
+    let counters = dev.fault_injector().unwrap().counters();
+    assert_eq!(counters.failed_lba_range, 1);
+
+    // The slot outside the range was served from the disk.
+    let mut sector = [0u8; SECTOR_SIZE];
+    mem.read_physical(READ_BUF, &mut sector);
+    assert_eq!(sector, [0u8; SECTOR_SIZE]);
+}
+
+fn ide_with_disk() -> Piix3IdePciDevice {
+    let mut ide = Piix3IdePciDevice::new();
+    ide.controller
+        .attach_primary_master_ata(AtaDrive::new(Box::new(patterned_disk())).unwrap());
+    ide.config_mut().set_command(0x0001); // IO decode
+    ide
+}
+
+fn ide_read_sectors(ide: &mut Piix3IdePciDevice, lba: u8) {
+    let base = PRIMARY_PORTS.cmd_base;
+    ide.io_write(base + 6, 1, 0xE0); // master + LBA
+    ide.io_write(base + 2, 1, 1);
+    ide.io_write(base + 3, 1, u32::from(lba));
+    ide.io_write(base + 4, 1, 0);
+    ide.io_write(base + 5, 1, 0);
+    ide.io_write(base + 7, 1, 0x20); // READ SECTORS
+}
+
+#[test]
+fn ide_delayed_command_stays_busy_until_due() {
+    let mut ide = ide_with_disk();
+    ide.controller
+        .set_fault_injector(Some(StorageFaultInjector::new(delay_policy())));
+
+    ide_read_sectors(&mut ide, 3);
+    assert_eq!(ide.controller.held_command_count(), 1);
+    let st = ide.io_read(PRIMARY_PORTS.ctrl_base, 1) as u8;
+    assert_eq!(st & 0x88, 0x80, "BSY set, DRQ clear while held");
+    // Status reads neither complete nor acknowledge the held command.
+    let _ = ide.io_read(PRIMARY_PORTS.cmd_base + 7, 1);
+    assert!(!ide.controller.primary_irq_pending());
+
+    ide.controller.set_time_ns(DELAY_NS);
+    assert_eq!(ide.controller.held_command_count(), 0);
+    assert!(ide.controller.primary_irq_pending());
+    let st = ide.io_read(PRIMARY_PORTS.ctrl_base, 1) as u8;
+    assert_eq!(st & 0x88, 0x08, "DRQ set once released");
+
+    let word = ide.io_read(PRIMARY_PORTS.cmd_base, 2) as u16;
+    assert_eq!(word, 0x0303);
+}
+
+#[test]
+fn ide_injected_unavailable_backend_reports_uncorrectable_error() {
+    let mut ide = ide_with_disk();
+    ide.controller
+        .set_fault_injector(Some(StorageFaultInjector::new(StorageFaultPolicy {
+            fail_every_nth: Some((1, StorageFaultError::BackendUnavailable)),
+            ..Default::default()
+        })));
+
+    ide_read_sectors(&mut ide, 0);
+    assert!(ide.controller.primary_irq_pending());
+    let st = ide.io_read(PRIMARY_PORTS.ctrl_base, 1) as u8;
+    assert_eq!(st & 0x89, 0x01, "ERR set, BSY/DRQ clear");
+    assert_eq!(ide.io_read(PRIMARY_PORTS.cmd_base + 1, 1), 0x40);
+}
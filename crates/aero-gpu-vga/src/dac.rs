@@ -0,0 +1,350 @@
+//! VGA DAC (ports `0x3C6..=0x3C9`) shared by every legacy VGA frontend.
+//!
+//! [`VgaDevice`](crate::VgaDevice), the AeroGPU legacy VGA decode and the BIOS palette mirror all
+//! program the palette through this type, so the port state machine, the 8-bit auto-detect policy,
+//! the default palette, the snapshot encoding and the 6-bit → 8-bit expansion used for scanout are
+//! the same everywhere.
+
+use crate::snapshot::{Result, VgaSnapshotError};
+
+/// Current version of the [`VgaDac::save_state`] encoding.
+const STATE_VERSION: u8 = 1;
+/// Length of the version 1 state: version, PEL mask, read index/subindex, write index/subindex,
+/// write latch and the 6-bit palette.
+pub const VGA_DAC_STATE_LEN: usize = 1 + 1 + 2 + 2 + 3 + 256 * 3;
+
+/// Default 256-color palette as VGA-native 6-bit `[r, g, b]` components.
+///
+/// Indices 0..=15 hold the EGA colors, 16..=231 a 6×6×6 color cube and 232..=255 a grayscale ramp.
+/// Cube and ramp levels are computed in 8-bit and truncated to 6 bits, which is also what the BIOS
+/// reports through INT 10h AX=4F09 "Get Palette Data".
+pub const DEFAULT_VGA_PALETTE_6BIT: [[u8; 3]; 256] = default_palette();
+
+const fn default_palette() -> [[u8; 3]; 256] {
+    const EGA: [[u8; 3]; 16] = [
+        [0, 0, 0],    // 0 black
+        [0, 0, 42],   // 1 blue
+        [0, 42, 0],   // 2 green
+        [0, 42, 42],  // 3 cyan
+        [42, 0, 0],   // 4 red
+        [42, 0, 42],  // 5 magenta
+        [42, 21, 0],  // 6 brown
+        [42, 42, 42], // 7 light grey
+        [21, 21, 21], // 8 dark grey
+        [21, 21, 63], // 9 bright blue
+        [21, 63, 21], // 10 bright green
+        [21, 63, 63], // 11 bright cyan
+        [63, 21, 21], // 12 bright red
+        [63, 21, 63], // 13 bright magenta
+        [63, 63, 21], // 14 yellow
+        [63, 63, 63], // 15 white
+    ];
+    const fn level(v: usize, steps: usize) -> u8 {
+        ((v * 255 / steps) >> 2) as u8
+    }
+
+    let mut pal = [[0u8; 3]; 256];
+    let mut i = 0;
+    while i < 16 {
+        pal[i] = EGA[i];
+        i += 1;
+    }
+    while i < 232 {
+        let c = i - 16;
+        pal[i] = [level(c / 36, 5), level((c / 6) % 6, 5), level(c % 6, 5)];
+        i += 1;
+    }
+    while i < 256 {
+        let v = level(i - 232, 23);
+        pal[i] = [v, v, v];
+        i += 1;
+    }
+    pal
+}
+
+/// Expand a 6-bit DAC component to 8 bits.
+pub fn vga_6bit_to_8bit(v: u8) -> u8 {
+    let v = v & 0x3F;
+    (v << 2) | (v >> 4)
+}
+
+/// VGA DAC register state and palette RAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VgaDac {
+    pub(crate) pel_mask: u8,
+    pub(crate) write_index: u8,
+    pub(crate) write_subindex: u8,
+    pub(crate) write_latch: [u8; 3],
+    pub(crate) read_index: u8,
+    pub(crate) read_subindex: u8,
+    /// Stored as VGA-native 6-bit components (0..=63).
+    pub(crate) palette: [[u8; 3]; 256],
+}
+
+impl Default for VgaDac {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VgaDac {
+    pub fn new() -> Self {
+        Self {
+            pel_mask: 0xFF,
+            write_index: 0,
+            write_subindex: 0,
+            write_latch: [0; 3],
+            read_index: 0,
+            read_subindex: 0,
+            palette: DEFAULT_VGA_PALETTE_6BIT,
+        }
+    }
+
+    /// Restore the default palette and PEL mask, keeping the port state machine as is.
+    pub fn reset_palette(&mut self) {
+        self.palette = DEFAULT_VGA_PALETTE_6BIT;
+        self.pel_mask = 0xFF;
+    }
+
+    pub fn pel_mask(&self) -> u8 {
+        self.pel_mask
+    }
+
+    pub fn set_pel_mask(&mut self, mask: u8) {
+        self.pel_mask = mask;
+    }
+
+    /// Palette RAM as 6-bit `[r, g, b]` components.
+    pub fn palette(&self) -> &[[u8; 3]; 256] {
+        &self.palette
+    }
+
+    pub fn set_entry(&mut self, index: u8, rgb: [u8; 3]) {
+        self.palette[usize::from(index)] = rgb.map(|c| c & 0x3F);
+    }
+
+    /// Restore the port state machine (as captured by older snapshot encodings).
+    pub fn restore_indices(
+        &mut self,
+        read_index: u8,
+        read_subindex: u8,
+        write_index: u8,
+        write_subindex: u8,
+        write_latch: [u8; 3],
+    ) {
+        self.read_index = read_index;
+        self.read_subindex = read_subindex % 3;
+        self.write_index = write_index;
+        self.write_subindex = write_subindex % 3;
+        self.write_latch = write_latch;
+    }
+
+    /// The palette as it is scanned out: entry `i` is the RGBA8888 color (`[R, G, B, 0xFF]` in
+    /// memory order) of pixel index `i` after applying the PEL mask.
+    pub fn palette_as_rgba8888(&self) -> [u32; 256] {
+        let mut out = [0u32; 256];
+        for (idx, px) in out.iter_mut().enumerate() {
+            let [r, g, b] = self.palette[usize::from(idx as u8 & self.pel_mask)];
+            *px = u32::from_le_bytes([
+                vga_6bit_to_8bit(r),
+                vga_6bit_to_8bit(g),
+                vga_6bit_to_8bit(b),
+                0xFF,
+            ]);
+        }
+        out
+    }
+
+    /// Read one of the DAC ports; other ports read as `0xFF`.
+    pub fn port_read(&mut self, port: u16) -> u8 {
+        match port {
+            0x3C6 => self.pel_mask,
+            0x3C7 => self.read_index,
+            0x3C8 => self.write_index,
+            0x3C9 => {
+                let out =
+                    self.palette[usize::from(self.read_index)][usize::from(self.read_subindex)];
+                self.read_subindex = (self.read_subindex + 1) % 3;
+                if self.read_subindex == 0 {
+                    self.read_index = self.read_index.wrapping_add(1);
+                }
+                out
+            }
+            _ => 0xFF,
+        }
+    }
+
+    /// Write one of the DAC ports; other ports are ignored.
+    ///
+    /// Returns whether the write changed the scanned-out colors (a PEL mask write or a completed
+    /// palette entry).
+    pub fn port_write(&mut self, port: u16, value: u8) -> bool {
+        match port {
+            0x3C6 => {
+                self.pel_mask = value;
+                true
+            }
+            0x3C7 => {
+                self.read_index = value;
+                self.read_subindex = 0;
+                false
+            }
+            0x3C8 => {
+                self.write_index = value;
+                self.write_subindex = 0;
+                false
+            }
+            0x3C9 => {
+                self.write_latch[usize::from(self.write_subindex)] = value;
+                self.write_subindex = (self.write_subindex + 1) % 3;
+                if self.write_subindex != 0 {
+                    return false;
+                }
+
+                // Real VGA hardware uses a 6-bit DAC, but a lot of guest software writes 8-bit
+                // components. If any component of the RGB triplet is > 63, treat the whole entry as
+                // 8-bit and downscale it with `>> 2`; otherwise treat it as 6-bit. This handles
+                // 8-bit palettes with dark components as long as one of R/G/B exceeds 63.
+                let is_8bit = self.write_latch.iter().any(|&v| v > 0x3F);
+                self.palette[usize::from(self.write_index)] =
+                    self.write_latch
+                        .map(|v| if is_8bit { v >> 2 } else { v & 0x3F });
+                self.write_index = self.write_index.wrapping_add(1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Program `entries` (INT 10h AX=4F09 layout: `B, G, R, 0` per entry) starting at `start`, as
+    /// the BIOS would through ports `0x3C8/0x3C9`. Components are 8-bit when `dac_width_bits >= 8`
+    /// and downscaled to 6 bits.
+    pub fn program_bgr0_entries(&mut self, start: u8, entries: &[u8], dac_width_bits: u8) {
+        self.port_write(0x3C8, start);
+        for entry in entries.chunks_exact(4) {
+            for c in [entry[2], entry[1], entry[0]] {
+                let c = if dac_width_bits >= 8 {
+                    c >> 2
+                } else {
+                    c & 0x3F
+                };
+                self.port_write(0x3C9, c);
+            }
+        }
+    }
+
+    /// Encode the DAC state. This is the snapshot encoding shared by all VGA frontends.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(VGA_DAC_STATE_LEN);
+        out.push(STATE_VERSION);
+        out.push(self.pel_mask);
+        out.push(self.read_index);
+        out.push(self.read_subindex);
+        out.push(self.write_index);
+        out.push(self.write_subindex);
+        out.extend_from_slice(&self.write_latch);
+        for rgb in &self.palette {
+            out.extend_from_slice(rgb);
+        }
+        out
+    }
+
+    /// Decode state produced by [`VgaDac::save_state`]. Bytes appended by newer versions are
+    /// ignored.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() < VGA_DAC_STATE_LEN {
+            return Err(VgaSnapshotError::Corrupt("truncated VGA DAC state"));
+        }
+        if bytes[0] == 0 {
+            return Err(VgaSnapshotError::Corrupt("invalid VGA DAC state version"));
+        }
+        self.pel_mask = bytes[1];
+        self.restore_indices(
+            bytes[2],
+            bytes[3],
+            bytes[4],
+            bytes[5],
+            [bytes[6], bytes[7], bytes[8]],
+        );
+        for (entry, rgb) in self
+            .palette
+            .iter_mut()
+            .zip(bytes[9..VGA_DAC_STATE_LEN].chunks_exact(3))
+        {
+            *entry = [rgb[0] & 0x3F, rgb[1] & 0x3F, rgb[2] & 0x3F];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_palette_levels() {
+        assert_eq!(DEFAULT_VGA_PALETTE_6BIT[16], [0, 0, 0]);
+        assert_eq!(DEFAULT_VGA_PALETTE_6BIT[17], [0, 0, 12]);
+        assert_eq!(
+            DEFAULT_VGA_PALETTE_6BIT[16 + 36 * 3 + 6 * 4 + 5],
+            [38, 51, 63]
+        );
+        assert_eq!(DEFAULT_VGA_PALETTE_6BIT[231], [63, 63, 63]);
+        assert_eq!(DEFAULT_VGA_PALETTE_6BIT[233], [2, 2, 2]);
+        assert_eq!(DEFAULT_VGA_PALETTE_6BIT[255], [63, 63, 63]);
+    }
+
+    #[test]
+    fn port_writes_auto_detect_8bit_entries() {
+        let mut dac = VgaDac::new();
+        dac.port_write(0x3C8, 5);
+        for v in [0x3F, 0x20, 0x00, 0xFF, 0x20, 0x00] {
+            dac.port_write(0x3C9, v);
+        }
+        assert_eq!(dac.palette()[5], [0x3F, 0x20, 0x00]);
+        assert_eq!(dac.palette()[6], [0x3F, 0x08, 0x00]);
+        assert_eq!(dac.port_read(0x3C8), 7);
+
+        dac.port_write(0x3C7, 6);
+        let read: Vec<u8> = (0..4).map(|_| dac.port_read(0x3C9)).collect();
+        assert_eq!(read, [0x3F, 0x08, 0x00, DEFAULT_VGA_PALETTE_6BIT[7][0]]);
+    }
+
+    #[test]
+    fn rgba_palette_applies_pel_mask() {
+        let mut dac = VgaDac::new();
+        dac.set_entry(0x13, [0x3F, 0x10, 0x01]);
+        dac.set_pel_mask(0x1F);
+        let pal = dac.palette_as_rgba8888();
+        assert_eq!(pal[0x13], 0xFF04_41FF);
+        assert_eq!(pal[0xF3], pal[0x13]);
+    }
+
+    #[test]
+    fn bgr0_entries_respect_dac_width() {
+        let mut dac = VgaDac::new();
+        dac.program_bgr0_entries(3, &[0x10, 0x20, 0x30, 0, 0x40, 0x80, 0xFC, 0], 6);
+        assert_eq!(dac.palette()[3], [0x30, 0x20, 0x10]);
+        assert_eq!(dac.palette()[4], [0x3C, 0x00, 0x00]);
+        dac.program_bgr0_entries(4, &[0x40, 0x80, 0xFC, 0], 8);
+        assert_eq!(dac.palette()[4], [0x3F, 0x20, 0x10]);
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let mut dac = VgaDac::new();
+        dac.set_pel_mask(0x7F);
+        dac.port_write(0x3C8, 0x80);
+        dac.port_write(0x3C9, 0x11);
+        dac.port_write(0x3C7, 0x22);
+        dac.port_read(0x3C9);
+
+        let state = dac.save_state();
+        assert_eq!(state.len(), VGA_DAC_STATE_LEN);
+        let mut restored = VgaDac::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored, dac);
+
+        assert!(restored.load_state(&state[..state.len() - 1]).is_err());
+    }
+}
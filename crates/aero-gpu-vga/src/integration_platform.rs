@@ -20,4 +20,8 @@ impl PortIoDevice for VgaPortIoDevice {
     fn write(&mut self, port: u16, size: u8, value: u32) {
         self.dev.borrow_mut().port_write(port, size as usize, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "vga"
+    }
 }
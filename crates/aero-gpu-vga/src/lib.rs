@@ -4,7 +4,8 @@
 //! of the emulator later. It provides:
 //! - VGA register file emulation (sequencer/graphics/attribute/CRTC) with the
 //!   subset of behavior needed for BIOS + early boot.
-//! - Text mode (80x25) rendering with a built-in bitmap font and cursor.
+//! - Text mode (80x25) rendering with a built-in bitmap font, cursor and blink (shared with
+//!   other legacy text renderers via [`TextFrame`]).
 //! - Mode 13h (320x200x256) rendering (chain-4).
 //! - A Bochs-compatible VBE ("VBE_DISPI") interface for linear framebuffer
 //!   modes commonly used by boot loaders/Windows boot splash.
@@ -17,18 +18,25 @@
 //! byte order in memory on little-endian is `[R, G, B, A]`, matching Canvas
 //! `ImageData`).
 
+mod dac;
 mod palette;
 mod snapshot;
 mod text_font;
+mod text_render;
 
 #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
 use aero_shared::scanout_state::{
     ScanoutStateUpdate, SCANOUT_FORMAT_B8G8R8X8, SCANOUT_SOURCE_LEGACY_TEXT,
     SCANOUT_SOURCE_LEGACY_VBE_LFB,
 };
+pub use dac::{vga_6bit_to_8bit, VgaDac, DEFAULT_VGA_PALETTE_6BIT, VGA_DAC_STATE_LEN};
 use palette::{rgb_to_rgba_u32, Rgb};
 pub use snapshot::{VgaSnapshotError, VgaSnapshotV1, VgaSnapshotV2};
 pub use text_font::FONT8X8_CP437;
+pub use text_render::{
+    attribute_palette_lookup, text_blink_epoch, text_char_width, TextBlinkPhase, TextCursor,
+    TextFrame, TEXT_BLINK_HALF_PERIOD_NS, TEXT_CHAR_HEIGHT, TEXT_CURSOR_BLINK_HALF_PERIOD_NS,
+};
 
 #[cfg(feature = "integration-memory")]
 mod integration_memory;
@@ -202,6 +210,24 @@ pub trait PortIO {
     fn port_write(&mut self, port: u16, size: usize, val: u32);
 }
 
+/// Text-mode geometry rendered by [`VgaDevice`].
+const TEXT_COLS: usize = 80;
+const TEXT_ROWS: usize = 25;
+
+/// Raw contents of the visible text page, returned by [`VgaDevice::text_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSnapshot {
+    pub cols: usize,
+    pub rows: usize,
+    /// Row-major `(char, attr)` pairs.
+    pub cells: Vec<(u8, u8)>,
+    /// Cursor `(row, col)` relative to the visible window; may lie outside it.
+    pub cursor: (usize, usize),
+    /// Attribute Controller Mode Control register (index 0x10), which selects blink vs. bright
+    /// background for attribute bit 7.
+    pub mode_control: u8,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RenderMode {
     Text80x25,
@@ -280,17 +306,11 @@ pub struct VgaDevice {
     /// Deterministic vblank clock used to model the VGA Input Status 1 vertical retrace bit.
     ///
     /// This advances via [`VgaDevice::tick`], which is wired to the machine's deterministic
-    /// `tick_platform` path.
+    /// `tick_platform` path. Text mode blink and cursor phases are derived from it as well.
     vblank_time_ns: u64,
 
     // DAC / palette.
-    pel_mask: u8,
-    dac_write_index: u8,
-    dac_write_subindex: u8,
-    dac_write_latch: [u8; 3],
-    dac_read_index: u8,
-    dac_read_subindex: u8,
-    dac: [Rgb; 256],
+    dac: VgaDac,
 
     // Bochs VBE.
     vbe_index: u16,
@@ -325,6 +345,8 @@ pub struct VgaDevice {
     width: u32,
     height: u32,
     dirty: bool,
+    /// Blink epoch the last text frame was rendered at, if that frame depends on the blink phase.
+    text_blink_epoch: Option<u64>,
 }
 
 impl Default for VgaDevice {
@@ -387,13 +409,7 @@ impl VgaDevice {
             attribute_ext: [0; 0x20],
             input_status1_vretrace: false,
             vblank_time_ns: 0,
-            pel_mask: 0xFF,
-            dac_write_index: 0,
-            dac_write_subindex: 0,
-            dac_write_latch: [0; 3],
-            dac_read_index: 0,
-            dac_read_subindex: 0,
-            dac: [Rgb::BLACK; 256],
+            dac: VgaDac::new(),
             vbe_index: 0,
             vbe: VbeRegs::default(),
             vbe_bytes_per_scan_line_override: 0,
@@ -405,9 +421,9 @@ impl VgaDevice {
             width: 0,
             height: 0,
             dirty: true,
+            text_blink_epoch: None,
         };
 
-        device.reset_palette();
         device.set_text_mode_80x25();
         device.present();
         device
@@ -415,12 +431,22 @@ impl VgaDevice {
 
     /// Advance the deterministic vblank clock.
     ///
-    /// This does not affect rendering (Aero's VGA model is not scanline-accurate), but it allows
-    /// legacy guests to poll the VGA status register (`0x3DA`) for vertical retrace pacing.
+    /// Aero's VGA model is not scanline-accurate, but this allows legacy guests to poll the VGA
+    /// status register (`0x3DA`) for vertical retrace pacing and drives text mode blinking.
     pub fn tick(&mut self, delta_ns: u64) {
         self.vblank_time_ns = self.vblank_time_ns.wrapping_add(delta_ns);
     }
 
+    /// Whether the last presented text frame shows blinking characters or a cursor whose blink
+    /// phase has changed since it was rendered.
+    ///
+    /// [`DisplayOutput::present`] re-renders such frames on its own; hosts that cache presented
+    /// frames can use this to decide whether to call it.
+    pub fn text_blink_refresh_pending(&self) -> bool {
+        self.text_blink_epoch
+            .is_some_and(|epoch| epoch != text_blink_epoch(self.vblank_time_ns))
+    }
+
     fn in_vblank(&self) -> bool {
         if Self::VBLANK_PERIOD_NS == 0 {
             return false;
@@ -480,6 +506,8 @@ impl VgaDevice {
                 height: 0,
                 pitch_bytes: 0,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             };
 
             let width = u32::from(self.vbe.xres);
@@ -583,6 +611,8 @@ impl VgaDevice {
                 height,
                 pitch_bytes,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             };
         }
 
@@ -597,12 +627,25 @@ impl VgaDevice {
             height: 0,
             pitch_bytes: 0,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         }
     }
     /// Resets the DAC to a sensible default VGA palette (EGA 16-color + 256-color cube).
     pub fn reset_palette(&mut self) {
-        self.dac = palette::default_vga_palette();
-        self.pel_mask = 0xFF;
+        self.dac.reset_palette();
+        self.dirty = true;
+    }
+
+    pub fn dac(&self) -> &VgaDac {
+        &self.dac
+    }
+
+    /// Mutable access to the DAC for host-side palette programming (e.g. mirroring BIOS palette
+    /// services); forces the next `present()` to re-render.
+    pub fn dac_mut(&mut self) -> &mut VgaDac {
+        self.dirty = true;
+        &mut self.dac
     }
 
     /// Convenience helper: configure the register file for VGA text mode 80x25.
@@ -1137,10 +1180,12 @@ impl VgaDevice {
 
     fn render(&mut self) {
         let mode = self.derived_render_mode();
+        self.text_blink_epoch = None;
         match mode {
             RenderMode::Text80x25 => {
-                self.ensure_buffers(80 * 9, 25 * 16);
-                self.render_text_mode();
+                let char_width = text_char_width(self.sequencer[1]);
+                self.ensure_buffers(80 * char_width as u32, 25 * TEXT_CHAR_HEIGHT as u32);
+                self.render_text_mode(char_width);
             }
             RenderMode::Mode13h => {
                 self.ensure_buffers(320, 200);
@@ -1157,17 +1202,9 @@ impl VgaDevice {
         }
     }
 
-    fn render_text_mode(&mut self) {
-        self.back.fill(0);
-        let cols = 80usize;
-        let rows = 25usize;
-        let cell_w = 9usize;
-        let cell_h = 16usize;
-        let width = self.width as usize;
-
-        let line_graphics_enable = (self.attribute[0x10] & (1 << 2)) != 0;
-        let blink_enabled = (self.attribute[0x10] & (1 << 3)) != 0;
-
+    /// Visible text window layout: `(start_addr, row_stride_cells, cursor_row, cursor_col)`, with
+    /// the start address in character cells.
+    fn text_layout(&self) -> (usize, usize, usize, usize) {
         // Text start address (CRTC regs 0x0C/0x0D) is specified in units of character cells.
         // Real VGA hardware uses only the low 14 bits and wraps within the 16KiB text window.
         let start_addr = (self.crtc_start_address_bytes() >> 1) & 0x3FFF;
@@ -1178,122 +1215,97 @@ impl VgaDevice {
             .crtc_offset_bytes()
             .and_then(|b| b.checked_div(2))
             .filter(|&v| v != 0)
-            .unwrap_or(cols);
-
-        for row in 0..rows {
-            for col in 0..cols {
-                let cell_index = row * row_stride_cells + col;
-                let mem_index = (start_addr + cell_index) & 0x3FFF;
-                let ch = self.vram[mem_index];
-                let attr = if self.config.legacy_plane_count >= 2 {
-                    self.vram[VGA_PLANE_SIZE + mem_index]
-                } else {
-                    0
-                };
-
-                let fg = attr & 0x0F;
-                let bg = if blink_enabled {
-                    (attr >> 4) & 0x07
-                } else {
-                    (attr >> 4) & 0x0F
-                };
-
-                let fg_dac = self.attribute_palette_lookup(fg);
-                let bg_dac = self.attribute_palette_lookup(bg);
-
-                // Apply the PEL mask (0x3C6) to the final DAC index like real VGA hardware.
-                let fg_px = rgb_to_rgba_u32(self.dac[(fg_dac & self.pel_mask) as usize]);
-                let bg_px = rgb_to_rgba_u32(self.dac[(bg_dac & self.pel_mask) as usize]);
-
-                for y in 0..cell_h {
-                    let glyph_row = self.font_row_8x16(ch, y as u8);
-                    let dst_y = row * cell_h + y;
-                    let dst_row_base = dst_y * width + col * cell_w;
-
-                    for x in 0..8 {
-                        let bit = (glyph_row >> (7 - x)) & 1;
-                        self.back[dst_row_base + x] = if bit != 0 { fg_px } else { bg_px };
-                    }
-
-                    // 9th column: replicate for box drawing range when enabled; otherwise background.
-                    let ninth_bit = if line_graphics_enable && (0xC0..=0xDF).contains(&ch) {
-                        glyph_row & 1
-                    } else {
-                        0
-                    };
-                    self.back[dst_row_base + 8] = if ninth_bit != 0 { fg_px } else { bg_px };
-                }
+            .unwrap_or(TEXT_COLS);
+
+        // The cursor location (CRTC 0x0E/0x0F) is an absolute cell address; map it back to a
+        // visible row/column relative to the start address.
+        let cursor_pos =
+            ((usize::from(self.crtc[0x0E]) << 8) | usize::from(self.crtc[0x0F])) & 0x3FFF;
+        let cursor_cell = cursor_pos.wrapping_sub(start_addr) & 0x3FFF;
+        (
+            start_addr,
+            row_stride_cells,
+            cursor_cell / row_stride_cells,
+            cursor_cell % row_stride_cells,
+        )
+    }
+
+    /// `(char, attr)` of the text cell at `row`/`col` of the visible window.
+    fn text_cell(
+        &self,
+        start_addr: usize,
+        row_stride_cells: usize,
+        row: usize,
+        col: usize,
+    ) -> (u8, u8) {
+        let mem_index = (start_addr + row * row_stride_cells + col) & 0x3FFF;
+        let ch = self.vram[mem_index];
+        let attr = if self.config.legacy_plane_count >= 2 {
+            self.vram[VGA_PLANE_SIZE + mem_index]
+        } else {
+            0
+        };
+        (ch, attr)
+    }
 
-                // Cursor overlay.
-                if self.cursor_visible_at(mem_index as u16) {
-                    let (start, end) = self.cursor_scanlines();
-                    if start <= end {
-                        for y in start..=end {
-                            if y >= cell_h as u8 {
-                                continue;
-                            }
-                            let dst_y = row * cell_h + y as usize;
-                            let dst_row_base = dst_y * width + col * cell_w;
-                            for x in 0..cell_w {
-                                let px = &mut self.back[dst_row_base + x];
-                                *px = if *px == fg_px { bg_px } else { fg_px };
-                            }
-                        }
-                    }
-                }
+    /// Snapshot the visible text-mode page (what [`VgaDevice::present`] would draw), or `None`
+    /// outside of text mode.
+    pub fn text_snapshot(&self) -> Option<TextSnapshot> {
+        if self.derived_render_mode() != RenderMode::Text80x25 {
+            return None;
+        }
+        let (start_addr, row_stride_cells, cursor_row, cursor_col) = self.text_layout();
+        let mut cells = Vec::with_capacity(TEXT_COLS * TEXT_ROWS);
+        for row in 0..TEXT_ROWS {
+            for col in 0..TEXT_COLS {
+                cells.push(self.text_cell(start_addr, row_stride_cells, row, col));
             }
         }
+        Some(TextSnapshot {
+            cols: TEXT_COLS,
+            rows: TEXT_ROWS,
+            cells,
+            cursor: (cursor_row, cursor_col),
+            mode_control: self.attribute[0x10],
+        })
     }
 
-    fn cursor_visible_at(&self, cell_index: u16) -> bool {
-        // Cursor disable bit is bit5 of cursor start register.
-        if (self.crtc[0x0A] & 0x20) != 0 {
-            return false;
-        }
-        let cursor_pos = (((self.crtc[0x0E] as u16) << 8) | self.crtc[0x0F] as u16) & 0x3FFF;
-        cursor_pos == (cell_index & 0x3FFF)
-    }
+    fn render_text_mode(&mut self, char_width: usize) {
+        let cols = TEXT_COLS;
+        let rows = TEXT_ROWS;
+        let (start_addr, row_stride_cells, cursor_row, cursor_col) = self.text_layout();
+        let cursor = TextCursor {
+            row: cursor_row,
+            col: cursor_col,
+            start: self.crtc[0x0A],
+            end: self.crtc[0x0B],
+        };
 
-    fn cursor_scanlines(&self) -> (u8, u8) {
-        let start = self.crtc[0x0A] & 0x1F;
-        let end = self.crtc[0x0B] & 0x1F;
-        (start, end)
-    }
+        let blink_epoch = text_blink_epoch(self.vblank_time_ns);
+        let frame = TextFrame {
+            cols,
+            rows,
+            char_width,
+            mode_control: self.attribute[0x10],
+            underline_location: self.crtc[0x14],
+            cursor: Some(cursor),
+            blink_epoch,
+        };
 
-    fn font_row_8x16(&self, ch: u8, row: u8) -> u8 {
-        let row8 = (row / 2) as usize;
-        FONT8X8_CP437[ch as usize][row8]
+        let palette = self.dac.palette_as_rgba8888();
+        let mut back = std::mem::take(&mut self.back);
+        let blink_sensitive = frame.render(
+            &mut back,
+            |row, col| self.text_cell(start_addr, row_stride_cells, row, col),
+            // The PEL mask (0x3C6) is applied to the final DAC index like on real VGA hardware.
+            |color| palette[self.attribute_palette_lookup(color) as usize],
+        );
+        self.back = back;
+        self.text_blink_epoch = blink_sensitive.then_some(blink_epoch);
     }
 
     fn attribute_palette_lookup(&self, color: u8) -> u8 {
-        // Attribute Controller indices.
-        const MODE_CONTROL: usize = 0x10;
-        const COLOR_PLANE_ENABLE: usize = 0x12;
-        const COLOR_SELECT: usize = 0x14;
-
-        // Mirror the VGA Attribute Controller palette mapping logic:
-        // - Color Plane Enable masks the 4-bit color index.
-        // - Palette registers provide a 6-bit "PEL" (0..=63).
-        // - When the Mode Control P54S bit is set, palette bits 5-4 are sourced from
-        //   Color Select bits 3-2 instead of the palette register.
-        // - The top 2 bits of the DAC index (7-6) come from Color Select bits 1-0.
-        let mode_control = self.attribute[MODE_CONTROL];
-        let color_plane_enable = self.attribute[COLOR_PLANE_ENABLE] & 0x0F;
-        let color_select = self.attribute[COLOR_SELECT];
-
-        let masked = (color & 0x0F) & color_plane_enable;
-
-        // Palette entry is 6-bit (0..=63).
-        let mut pel = self.attribute[masked as usize] & 0x3F;
-
-        // VGA "Palette bits 5-4 select" (P54S): when set, bits 5-4 of the palette entry come from
-        // Color Select bits 3-2 instead of the palette register.
-        if (mode_control & 0x80) != 0 {
-            pel = (pel & 0x0F) | ((color_select & 0x0C) << 2);
-        }
-
-        // Bits 7-6 of the final DAC index come from Color Select bits 1-0.
-        ((color_select & 0x03) << 6) | pel
+        attribute_palette_lookup(color, &self.attribute)
     }
 
     fn render_mode_13h(&mut self) {
@@ -1308,6 +1320,7 @@ impl VgaDevice {
             .crtc_offset_bytes()
             .and_then(|bytes_per_plane| bytes_per_plane.checked_mul(4))
             .unwrap_or(width);
+        let palette = self.dac.palette_as_rgba8888();
         for y in 0..height {
             for x in 0..width {
                 let dst_linear = y * width + x;
@@ -1320,8 +1333,7 @@ impl VgaDevice {
                 } else {
                     0
                 };
-                let color = self.dac[(idx & self.pel_mask) as usize];
-                self.back[dst_linear] = rgb_to_rgba_u32(color);
+                self.back[dst_linear] = palette[idx as usize];
             }
         }
     }
@@ -1334,6 +1346,7 @@ impl VgaDevice {
             .crtc_offset_bytes()
             .unwrap_or_else(|| width_usize.div_ceil(8));
         let start = self.crtc_start_address_bytes();
+        let palette = self.dac.palette_as_rgba8888();
 
         for y in 0..height_usize {
             for x in 0..width_usize {
@@ -1349,9 +1362,9 @@ impl VgaDevice {
                     let v = (b >> bit) & 1;
                     color |= v << plane;
                 }
-                // Apply the PEL mask (0x3C6) to the final DAC index.
-                let dac_idx = self.attribute_palette_lookup(color) & self.pel_mask;
-                self.back[y * width_usize + x] = rgb_to_rgba_u32(self.dac[dac_idx as usize]);
+                // `palette` applies the PEL mask (0x3C6) to the final DAC index.
+                self.back[y * width_usize + x] =
+                    palette[self.attribute_palette_lookup(color) as usize];
             }
         }
     }
@@ -1385,6 +1398,7 @@ impl VgaDevice {
         if stride_bytes == 0 || bytes_per_pixel_usize == 0 {
             return;
         }
+        let palette = self.dac.palette_as_rgba8888();
 
         // Compute the displayed base address within VRAM:
         //   base = fb_base + y_offset * bytes_per_scan_line + x_offset * bytes_per_pixel
@@ -1456,10 +1470,7 @@ impl VgaDevice {
                             b: (b << 3) | (b >> 2),
                         })
                     }
-                    8 => {
-                        let idx = *self.vram.get(src).unwrap_or(&0);
-                        rgb_to_rgba_u32(self.dac[(idx & self.pel_mask) as usize])
-                    }
+                    8 => palette[usize::from(*self.vram.get(src).unwrap_or(&0))],
                     _ => 0,
                 };
                 self.back[dst_row + x as usize] = px;
@@ -1467,61 +1478,6 @@ impl VgaDevice {
         }
     }
 
-    fn write_dac_data(&mut self, value: u8) {
-        let idx = self.dac_write_index as usize;
-        let component = (self.dac_write_subindex as usize) % 3;
-
-        // Real VGA hardware uses a 6-bit DAC. In practice, a lot of guest software writes
-        // 8-bit (0..=255) component values directly. We support both by detecting 8-bit mode per
-        // palette entry:
-        //
-        // - If any component in the 3-byte RGB sequence is > 63, treat the whole entry as 8-bit
-        //   and downscale all components via `>> 2`.
-        // - Otherwise, treat the entry as 6-bit.
-        //
-        // This handles common 8-bit palettes where some components are <= 63 (dark) as long as
-        // at least one of R/G/B exceeds 63 within the same entry.
-        self.dac_write_latch[component] = value;
-
-        self.dac_write_subindex = (self.dac_write_subindex + 1) % 3;
-        if self.dac_write_subindex != 0 {
-            return;
-        }
-
-        let is_8bit = self.dac_write_latch.iter().any(|&v| v > 0x3F);
-        let to_vga_6bit = |v: u8| -> u8 {
-            if is_8bit {
-                v >> 2
-            } else {
-                v & 0x3F
-            }
-        };
-
-        let r = palette::vga_6bit_to_8bit(to_vga_6bit(self.dac_write_latch[0]));
-        let g = palette::vga_6bit_to_8bit(to_vga_6bit(self.dac_write_latch[1]));
-        let b = palette::vga_6bit_to_8bit(to_vga_6bit(self.dac_write_latch[2]));
-        self.dac[idx] = Rgb { r, g, b };
-
-        self.dac_write_index = self.dac_write_index.wrapping_add(1);
-        self.dirty = true;
-    }
-
-    fn read_dac_data(&mut self) -> u8 {
-        let idx = self.dac_read_index as usize;
-        let component = self.dac_read_subindex;
-        let v = match component {
-            0 => palette::vga_8bit_to_6bit(self.dac[idx].r),
-            1 => palette::vga_8bit_to_6bit(self.dac[idx].g),
-            2 => palette::vga_8bit_to_6bit(self.dac[idx].b),
-            _ => 0,
-        };
-        self.dac_read_subindex = (self.dac_read_subindex + 1) % 3;
-        if self.dac_read_subindex == 0 {
-            self.dac_read_index = self.dac_read_index.wrapping_add(1);
-        }
-        v
-    }
-
     fn vbe_read_reg(&self, index: u16) -> u16 {
         match index {
             0x0000 => 0xB0C5, // ID
@@ -1600,7 +1556,7 @@ impl DisplayOutput for VgaDevice {
     }
 
     fn present(&mut self) {
-        if !self.dirty {
+        if !self.dirty && !self.text_blink_refresh_pending() {
             return;
         }
         self.render();
@@ -1725,10 +1681,7 @@ impl VgaDevice {
             }
 
             // DAC.
-            0x3C6 => self.pel_mask,
-            0x3C7 => self.dac_read_index,
-            0x3C8 => self.dac_write_index,
-            0x3C9 => self.read_dac_data(),
+            0x3C6..=0x3C9 => self.dac.port_read(port),
 
             // Unimplemented ports.
             _ => 0xFF,
@@ -1784,19 +1737,11 @@ impl VgaDevice {
             }
 
             // DAC.
-            0x3C6 => {
-                self.pel_mask = val;
-                self.dirty = true;
-            }
-            0x3C7 => {
-                self.dac_read_index = val;
-                self.dac_read_subindex = 0;
-            }
-            0x3C8 => {
-                self.dac_write_index = val;
-                self.dac_write_subindex = 0;
+            0x3C6..=0x3C9 => {
+                if self.dac.port_write(port, val) {
+                    self.dirty = true;
+                }
             }
-            0x3C9 => self.write_dac_data(val),
 
             // Writes to unimplemented ports are ignored.
             _ => {}
@@ -1883,17 +1828,11 @@ impl IoSnapshot for VgaDevice {
         const TAG_ATTRIBUTE_FLIP_FLOP: u16 = 9;
         const TAG_ATTRIBUTE: u16 = 10;
         const TAG_INPUT_STATUS1_VRETRACE: u16 = 11;
-        const TAG_PEL_MASK: u16 = 12;
-        const TAG_DAC_WRITE_INDEX: u16 = 13;
-        const TAG_DAC_WRITE_SUBINDEX: u16 = 14;
-        const TAG_DAC_READ_INDEX: u16 = 15;
-        const TAG_DAC_READ_SUBINDEX: u16 = 16;
-        const TAG_DAC: u16 = 17;
+        // Tags 12..=17 and 22 held the DAC registers before `TAG_DAC_STATE`.
         const TAG_VBE_INDEX: u16 = 18;
         const TAG_VBE_REGS: u16 = 19;
         const TAG_VRAM: u16 = 20;
         const TAG_LATCHES: u16 = 21;
-        const TAG_DAC_WRITE_LATCH: u16 = 22;
         const TAG_VBLANK_TIME_NS: u16 = 23;
         const TAG_VBE_BYTES_PER_SCAN_LINE_OVERRIDE: u16 = 24;
         const TAG_SEQUENCER_EXT: u16 = 25;
@@ -1902,6 +1841,7 @@ impl IoSnapshot for VgaDevice {
         const TAG_ATTRIBUTE_EXT: u16 = 28;
         const TAG_LFB_BASE: u16 = 29;
         const TAG_VRAM_LEN: u16 = 30;
+        const TAG_DAC_STATE: u16 = 31;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -1925,21 +1865,8 @@ impl IoSnapshot for VgaDevice {
         w.field_bytes(TAG_ATTRIBUTE_EXT, self.attribute_ext.to_vec());
         w.field_bool(TAG_INPUT_STATUS1_VRETRACE, self.input_status1_vretrace);
 
-        w.field_u8(TAG_PEL_MASK, self.pel_mask);
-        w.field_u8(TAG_DAC_WRITE_INDEX, self.dac_write_index);
-        w.field_u8(TAG_DAC_WRITE_SUBINDEX, self.dac_write_subindex);
-        w.field_u8(TAG_DAC_READ_INDEX, self.dac_read_index);
-        w.field_u8(TAG_DAC_READ_SUBINDEX, self.dac_read_subindex);
-        w.field_bytes(TAG_DAC_WRITE_LATCH, self.dac_write_latch.to_vec());
-
-        // Palette: pack as tightly as possible (RGB triplets).
-        let mut pal = Vec::with_capacity(256 * 3);
-        for rgb in &self.dac {
-            pal.push(rgb.r);
-            pal.push(rgb.g);
-            pal.push(rgb.b);
-        }
-        w.field_bytes(TAG_DAC, pal);
+        // Same encoding as the AeroGPU legacy VGA DAC (see `VgaDac::save_state`).
+        w.field_bytes(TAG_DAC_STATE, self.dac.save_state());
 
         w.field_u16(TAG_VBE_INDEX, self.vbe_index);
         w.field_bytes(
@@ -2008,6 +1935,7 @@ impl IoSnapshot for VgaDevice {
         const TAG_ATTRIBUTE_EXT: u16 = 28;
         const TAG_LFB_BASE: u16 = 29;
         const TAG_VRAM_LEN: u16 = 30;
+        const TAG_DAC_STATE: u16 = 31;
 
         let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
         r.ensure_device_major(Self::DEVICE_VERSION.major)?;
@@ -2109,38 +2037,41 @@ impl IoSnapshot for VgaDevice {
             self.input_status1_vretrace = v;
         }
 
-        if let Some(v) = r.u8(TAG_PEL_MASK)? {
-            self.pel_mask = v;
-        }
-        if let Some(v) = r.u8(TAG_DAC_WRITE_INDEX)? {
-            self.dac_write_index = v;
-        }
-        if let Some(v) = r.u8(TAG_DAC_WRITE_SUBINDEX)? {
-            self.dac_write_subindex = v;
-        }
-        if let Some(buf) = r.bytes(TAG_DAC_WRITE_LATCH) {
-            if buf.len() != self.dac_write_latch.len() {
-                return Err(SnapshotError::InvalidFieldEncoding("dac_write_latch"));
+        if let Some(buf) = r.bytes(TAG_DAC_STATE) {
+            self.dac
+                .load_state(buf)
+                .map_err(|_| SnapshotError::InvalidFieldEncoding("dac_state"))?;
+        } else {
+            // Older snapshots store the DAC registers individually and the palette as 8-bit
+            // components.
+            if let Some(v) = r.u8(TAG_PEL_MASK)? {
+                self.dac.pel_mask = v;
             }
-            self.dac_write_latch.copy_from_slice(buf);
-        }
-        if let Some(v) = r.u8(TAG_DAC_READ_INDEX)? {
-            self.dac_read_index = v;
-        }
-        if let Some(v) = r.u8(TAG_DAC_READ_SUBINDEX)? {
-            self.dac_read_subindex = v;
-        }
-        if let Some(buf) = r.bytes(TAG_DAC) {
-            if buf.len() != 256 * 3 {
-                return Err(SnapshotError::InvalidFieldEncoding("dac"));
+            if let Some(v) = r.u8(TAG_DAC_WRITE_INDEX)? {
+                self.dac.write_index = v;
             }
-            for i in 0..256 {
-                let base = i * 3;
-                self.dac[i] = Rgb {
-                    r: buf[base],
-                    g: buf[base + 1],
-                    b: buf[base + 2],
-                };
+            if let Some(v) = r.u8(TAG_DAC_WRITE_SUBINDEX)? {
+                self.dac.write_subindex = v % 3;
+            }
+            if let Some(buf) = r.bytes(TAG_DAC_WRITE_LATCH) {
+                if buf.len() != self.dac.write_latch.len() {
+                    return Err(SnapshotError::InvalidFieldEncoding("dac_write_latch"));
+                }
+                self.dac.write_latch.copy_from_slice(buf);
+            }
+            if let Some(v) = r.u8(TAG_DAC_READ_INDEX)? {
+                self.dac.read_index = v;
+            }
+            if let Some(v) = r.u8(TAG_DAC_READ_SUBINDEX)? {
+                self.dac.read_subindex = v % 3;
+            }
+            if let Some(buf) = r.bytes(TAG_DAC) {
+                if buf.len() != 256 * 3 {
+                    return Err(SnapshotError::InvalidFieldEncoding("dac"));
+                }
+                for (entry, rgb) in self.dac.palette.iter_mut().zip(buf.chunks_exact(3)) {
+                    *entry = [rgb[0] >> 2, rgb[1] >> 2, rgb[2] >> 2];
+                }
             }
         }
 
@@ -2371,8 +2302,8 @@ mod tests {
         let fb = dev.get_framebuffer();
         let width = dev.get_resolution().0 as usize;
         // Use the 9th column of each cell (x=8) which is always background for normal glyphs.
-        assert_eq!(fb[8], rgb_to_rgba_u32(dev.dac[1]));
-        assert_eq!(fb[16 * width + 8], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(fb[8], dev.dac.palette_as_rgba8888()[1]);
+        assert_eq!(fb[16 * width + 8], dev.dac.palette_as_rgba8888()[2]);
     }
 
     #[test]
@@ -2393,7 +2324,7 @@ mod tests {
         dev.mem_write_u8(base + 3, 0x20);
 
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[1]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[1]);
 
         // Default CRTC byte mode is off; start address is interpreted as a word offset, so
         // start=1 selects cell 1.
@@ -2401,19 +2332,45 @@ mod tests {
         dev.crtc[0x0D] = 1;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[2]);
 
         // Enable CRTC byte mode (0x17 bit6). Now start=1 is a byte offset and rounds down to cell 0.
         dev.crtc[0x17] |= 0x40;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[1]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[1]);
 
         // start=2 bytes selects cell 1.
         dev.crtc[0x0D] = 2;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[2]);
+    }
+
+    #[test]
+    fn text_snapshot_follows_start_address_and_cursor() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+
+        let base = 0xB8000u32;
+        dev.mem_write_u8(base + 0x1000, b'P');
+        dev.mem_write_u8(base + 0x1001, 0x1F);
+
+        // Page 1 (0x800 cells) with the cursor on row 2, column 5 of that page.
+        dev.crtc[0x0C] = 0x08;
+        dev.crtc[0x0D] = 0x00;
+        let cursor = 0x800 + 2 * 80 + 5;
+        dev.crtc[0x0E] = (cursor >> 8) as u8;
+        dev.crtc[0x0F] = cursor as u8;
+
+        let snap = dev.text_snapshot().unwrap();
+        assert_eq!((snap.cols, snap.rows), (80, 25));
+        assert_eq!(snap.cells.len(), 80 * 25);
+        assert_eq!(snap.cells[0], (b'P', 0x1F));
+        assert_eq!(snap.cursor, (2, 5));
+
+        dev.set_mode_13h();
+        assert!(dev.text_snapshot().is_none());
     }
 
     #[test]
@@ -2459,8 +2416,8 @@ mod tests {
         let fg = attr & 0x0F;
         let bg = (attr >> 4) & 0x0F; // blink is disabled in set_text_mode_80x25()
 
-        let fg_px = rgb_to_rgba_u32(dev.dac[dev.attribute_palette_lookup(fg) as usize]);
-        let bg_px = rgb_to_rgba_u32(dev.dac[dev.attribute_palette_lookup(bg) as usize]);
+        let fg_px = dev.dac.palette_as_rgba8888()[dev.attribute_palette_lookup(fg) as usize];
+        let bg_px = dev.dac.palette_as_rgba8888()[dev.attribute_palette_lookup(bg) as usize];
         assert_ne!(fg_px, bg_px);
 
         let fb = dev.get_framebuffer();
@@ -2500,6 +2457,191 @@ mod tests {
         );
     }
 
+    fn write_text_cells(dev: &mut VgaDevice, cells: &[(u8, u8)]) {
+        for (i, &(ch, attr)) in cells.iter().enumerate() {
+            let addr = 0xB8000u32 + (i as u32) * 2;
+            dev.mem_write_u8(addr, ch);
+            dev.mem_write_u8(addr + 1, attr);
+        }
+    }
+
+    fn text_cell_pixels(dev: &VgaDevice, col: usize) -> Vec<u32> {
+        let (width, _) = dev.get_resolution();
+        let cell_w = width as usize / 80;
+        let fb = dev.get_framebuffer();
+        (0..16)
+            .flat_map(|y| {
+                let base = y * width as usize + col * cell_w;
+                fb[base..base + cell_w].iter().copied()
+            })
+            .collect()
+    }
+
+    const BOX_DRAWING_CELLS: [(u8, u8); 9] = [
+        (0xC9, 0x1F), // '╔'
+        (0xCD, 0x1F), // '═'
+        (0xBB, 0x1F), // '╗' (outside the line graphics range)
+        (0xC4, 0x2E), // '─'
+        (0xC5, 0x2E), // '┼'
+        (0xB3, 0x2E), // '│' (outside the line graphics range)
+        (0xDB, 0x4F), // '█'
+        (0xDF, 0x4F), // '▀'
+        (0xB0, 0x4F), // '░'
+    ];
+
+    #[test]
+    fn text_mode_box_drawing_golden_hash_9_dot() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+        dev.crtc[0x0A] = 0x20;
+        write_text_cells(&mut dev, &BOX_DRAWING_CELLS);
+
+        dev.present();
+        assert_eq!(dev.get_resolution(), (720, 400));
+        assert_eq!(framebuffer_hash(&dev), 0xf7cd62db55744bc5);
+
+        // '═' (0xCD) joins its neighbours through the 9th column; '╗' (0xBB) does not.
+        let fg = dev.dac.palette_as_rgba8888()[0x0F];
+        let bg = dev.dac.palette_as_rgba8888()[0x01];
+        let horiz = text_cell_pixels(&dev, 1);
+        assert!((0..16).any(|y| horiz[y * 9 + 8] == fg));
+        let corner = text_cell_pixels(&dev, 2);
+        assert!((0..16).all(|y| corner[y * 9 + 8] == bg));
+
+        // Disabling line graphics blanks the 9th column for the whole range.
+        dev.attribute[0x10] &= !(1 << 2);
+        dev.dirty = true;
+        dev.present();
+        let horiz = text_cell_pixels(&dev, 1);
+        assert!((0..16).all(|y| horiz[y * 9 + 8] == bg));
+    }
+
+    #[test]
+    fn text_mode_box_drawing_golden_hash_8_dot() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+        dev.crtc[0x0A] = 0x20;
+        write_text_cells(&mut dev, &BOX_DRAWING_CELLS);
+
+        // Sequencer Clocking Mode bit 0 selects 8-dot characters.
+        dev.port_write(0x3C4, 1, 0x01);
+        dev.port_write(0x3C5, 1, 0x01);
+        dev.present();
+        assert_eq!(dev.get_resolution(), (640, 400));
+        assert_eq!(framebuffer_hash(&dev), 0x90cea499d167147d);
+
+        let cell = text_cell_pixels(&dev, 1);
+        assert_eq!(cell.len(), 8 * 16);
+    }
+
+    #[test]
+    fn text_mode_blink_attribute_toggles_with_guest_time() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+        dev.crtc[0x0A] = 0x20;
+        // Attribute Mode Control bit 3: attribute bit 7 selects blink instead of intensity.
+        dev.attribute[0x10] |= 1 << 3;
+        write_text_cells(&mut dev, &[(0xDB, 0xCF), (0xDB, 0x4F)]);
+
+        dev.present();
+        let fg = dev.dac.palette_as_rgba8888()[0x0F];
+        // With blink enabled the background only uses 3 bits: 0xC -> 0x4.
+        let bg = dev.dac.palette_as_rgba8888()[0x04];
+        let visible = text_cell_pixels(&dev, 0);
+        assert!(visible.iter().all(|&px| px == fg));
+        assert_eq!(text_cell_pixels(&dev, 1), visible);
+
+        // Presents one second of guest time apart observe opposite blink phases.
+        dev.tick(1_000_000_000);
+        assert!(dev.text_blink_refresh_pending());
+        dev.present();
+        assert!(text_cell_pixels(&dev, 0).iter().all(|&px| px == bg));
+        assert_eq!(text_cell_pixels(&dev, 1), visible);
+
+        dev.tick(1_000_000_000);
+        dev.present();
+        assert_eq!(text_cell_pixels(&dev, 0), visible);
+
+        // With blink disabled, bit 7 selects a high-intensity background and nothing blinks.
+        dev.attribute[0x10] &= !(1 << 3);
+        write_text_cells(&mut dev, &[(b' ', 0xCF)]);
+        dev.present();
+        assert_eq!(
+            text_cell_pixels(&dev, 0)[0],
+            dev.dac.palette_as_rgba8888()[0x0C]
+        );
+        dev.tick(1_000_000_000);
+        assert!(!dev.text_blink_refresh_pending());
+    }
+
+    #[test]
+    fn text_mode_cursor_blinks_using_crtc_scanlines() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+        write_text_cells(&mut dev, &[(b'A', 0x07), (b' ', 0x07)]);
+
+        // Underline-style cursor on scanlines 14-15 at cell 1.
+        dev.crtc[0x0A] = 14;
+        dev.crtc[0x0B] = 15;
+        dev.crtc[0x0F] = 1;
+        dev.dirty = true;
+        dev.present();
+        let cursor_on = framebuffer_hash(&dev);
+        assert_eq!(cursor_on, 0x8c167fecd9d156e5);
+
+        let fg = dev.dac.palette_as_rgba8888()[0x07];
+        let bg = dev.dac.palette_as_rgba8888()[0x00];
+        let cell = text_cell_pixels(&dev, 1);
+        assert!(cell[13 * 9..14 * 9].iter().all(|&px| px == bg));
+        assert!(cell[14 * 9..16 * 9].iter().all(|&px| px == fg));
+
+        // The cursor toggles every 100ms of guest time.
+        dev.tick(TEXT_CURSOR_BLINK_HALF_PERIOD_NS);
+        dev.present();
+        let cursor_off = framebuffer_hash(&dev);
+        assert_eq!(cursor_off, 0x016efb69694eb665);
+        assert!(text_cell_pixels(&dev, 1)[14 * 9..16 * 9]
+            .iter()
+            .all(|&px| px == bg));
+
+        dev.tick(TEXT_CURSOR_BLINK_HALF_PERIOD_NS);
+        dev.present();
+        assert_eq!(framebuffer_hash(&dev), cursor_on);
+
+        // A start scanline past the end scanline hides the cursor without blink refreshes.
+        dev.crtc[0x0A] = 15;
+        dev.crtc[0x0B] = 14;
+        dev.dirty = true;
+        dev.present();
+        assert_eq!(framebuffer_hash(&dev), cursor_off);
+        dev.tick(TEXT_CURSOR_BLINK_HALF_PERIOD_NS);
+        assert!(!dev.text_blink_refresh_pending());
+    }
+
+    #[test]
+    fn text_mode_underline_attribute_in_monochrome_emulation() {
+        let mut dev = VgaDevice::new();
+        dev.set_text_mode_80x25();
+        dev.crtc[0x0A] = 0x20;
+        dev.crtc[0x14] = 13;
+        write_text_cells(&mut dev, &[(b' ', 0x01), (b' ', 0x02)]);
+
+        dev.present();
+        let bg = dev.dac.palette_as_rgba8888()[0x00];
+        assert!(text_cell_pixels(&dev, 0).iter().all(|&px| px == bg));
+
+        // Attribute Mode Control bit 1: monochrome emulation enables the underline attribute.
+        dev.attribute[0x10] |= 1 << 1;
+        dev.dirty = true;
+        dev.present();
+        let fg = dev.dac.palette_as_rgba8888()[0x01];
+        let cell = text_cell_pixels(&dev, 0);
+        assert!(cell[13 * 9..14 * 9].iter().all(|&px| px == fg));
+        assert!(cell[12 * 9..13 * 9].iter().all(|&px| px == bg));
+        // Only foreground 1 on background 0 is underlined.
+        assert!(text_cell_pixels(&dev, 1).iter().all(|&px| px == bg));
+    }
+
     #[test]
     fn mode13h_golden_hash() {
         let mut dev = VgaDevice::new();
@@ -2518,7 +2660,7 @@ mod tests {
         dev.present();
         assert_eq!(dev.get_resolution(), (320, 200));
         let hash_before = framebuffer_hash(&dev);
-        assert_eq!(hash_before, 0xf54b1d9c21a2a115);
+        assert_eq!(hash_before, 0x965d352eb85358d5);
 
         // Now enable a VBE LFB mode and write a pixel into the LFB. This must not clobber VGA plane
         // storage; if the LFB overlaps a VGA plane, switching back to mode 13h would change the
@@ -2562,13 +2704,13 @@ mod tests {
         dev.crtc[0x0D] = 1;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[2]);
 
         // Enable CRTC byte mode (0x17 bit6); now start=1 shifts by 1 byte (1 pixel).
         dev.crtc[0x17] |= 0x40;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[1]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[1]);
     }
 
     #[test]
@@ -2589,8 +2731,8 @@ mod tests {
         dev.present();
 
         assert_eq!(dev.get_resolution(), (320, 200));
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[1]));
-        assert_eq!(dev.get_framebuffer()[320], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[1]);
+        assert_eq!(dev.get_framebuffer()[320], dev.dac.palette_as_rgba8888()[2]);
     }
 
     #[test]
@@ -2643,13 +2785,13 @@ mod tests {
         dev.dirty = true;
         dev.present();
         assert_eq!(dev.get_resolution(), (8, 1));
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[2]);
 
         // Byte mode disabled: start=1 is a word offset => start_byte=2 selects byte2.
         dev.crtc[0x17] &= !0x40;
         dev.dirty = true;
         dev.present();
-        assert_eq!(dev.get_framebuffer()[0], rgb_to_rgba_u32(dev.dac[4]));
+        assert_eq!(dev.get_framebuffer()[0], dev.dac.palette_as_rgba8888()[4]);
     }
 
     #[test]
@@ -2680,8 +2822,8 @@ mod tests {
         dev.present();
         assert_eq!(dev.get_resolution(), (8, 2));
         let fb = dev.get_framebuffer();
-        assert_eq!(fb[0], rgb_to_rgba_u32(dev.dac[1]));
-        assert_eq!(fb[8], rgb_to_rgba_u32(dev.dac[2]));
+        assert_eq!(fb[0], dev.dac.palette_as_rgba8888()[1]);
+        assert_eq!(fb[8], dev.dac.palette_as_rgba8888()[2]);
     }
 
     #[test]
@@ -2800,6 +2942,37 @@ mod tests {
         assert_eq!(restored.get_framebuffer()[0], 0xFF00_00FF);
     }
 
+    #[test]
+    fn io_snapshot_loads_legacy_dac_tags() {
+        // Tags must match the `IoSnapshot` implementation above.
+        const TAG_PEL_MASK: u16 = 12;
+        const TAG_DAC_WRITE_INDEX: u16 = 13;
+        const TAG_DAC: u16 = 17;
+
+        // Older snapshots stored the palette as expanded 8-bit components.
+        let mut pal = Vec::with_capacity(256 * 3);
+        for i in 0..=255u8 {
+            let c = vga_6bit_to_8bit(i);
+            pal.extend_from_slice(&[c, 0xFF, 0]);
+        }
+        let mut w = SnapshotWriter::new(*b"VGAD", SnapshotVersion::new(1, 0));
+        w.field_u8(TAG_PEL_MASK, 0x0F);
+        w.field_u8(TAG_DAC_WRITE_INDEX, 0x42);
+        w.field_bytes(TAG_DAC, pal);
+        let snapshot = w.finish();
+
+        let mut dev = VgaDevice::new();
+        dev.load_state(&snapshot).unwrap();
+        assert_eq!(dev.dac().pel_mask(), 0x0F);
+        assert_eq!(dev.dac().palette()[0x2A], [0x2A, 0x3F, 0]);
+        assert_eq!(dev.port_read(0x3C8, 1), 0x42);
+
+        // Re-saving uses the shared DAC encoding and restores the same state.
+        let mut restored = VgaDevice::new();
+        restored.load_state(&dev.save_state()).unwrap();
+        assert_eq!(restored.dac(), dev.dac());
+    }
+
     #[test]
     fn legacy_vga_snapshot_v1_migrates_vbe_framebuffer_to_offset() {
         // Build a legacy `VgaSnapshotV1` that represents the old VRAM layout: VBE framebuffer at
@@ -1,6 +1,6 @@
 use std::io::{Cursor, Read};
 
-use crate::palette::Rgb;
+use crate::dac::vga_6bit_to_8bit;
 use crate::VgaDevice;
 
 /// Errors returned when decoding VGA snapshot payloads.
@@ -284,10 +284,7 @@ impl VgaDevice {
     ///
     /// Note: includes the full VRAM contents (16MiB by default).
     pub fn snapshot_v1(&self) -> VgaSnapshotV1 {
-        let mut dac = [[0u8; 3]; 256];
-        for (dst, src) in dac.iter_mut().zip(self.dac.iter()) {
-            *dst = [src.r, src.g, src.b];
-        }
+        let dac = self.dac.palette.map(|rgb| rgb.map(vga_6bit_to_8bit));
 
         VgaSnapshotV1 {
             misc_output: self.misc_output,
@@ -301,11 +298,11 @@ impl VgaDevice {
             attribute_flip_flop_data: self.attribute_flip_flop_data,
             attribute: self.attribute,
             input_status1_vretrace: self.input_status1_vretrace,
-            pel_mask: self.pel_mask,
-            dac_write_index: self.dac_write_index,
-            dac_write_subindex: self.dac_write_subindex,
-            dac_read_index: self.dac_read_index,
-            dac_read_subindex: self.dac_read_subindex,
+            pel_mask: self.dac.pel_mask,
+            dac_write_index: self.dac.write_index,
+            dac_write_subindex: self.dac.write_subindex,
+            dac_read_index: self.dac.read_index,
+            dac_read_subindex: self.dac.read_subindex,
             dac,
             vbe_index: self.vbe_index,
             vbe_xres: self.vbe.xres,
@@ -344,13 +341,13 @@ impl VgaDevice {
         out.extend_from_slice(&self.attribute);
         out.push(self.input_status1_vretrace as u8);
 
-        out.push(self.pel_mask);
-        out.push(self.dac_write_index);
-        out.push(self.dac_write_subindex);
-        out.push(self.dac_read_index);
-        out.push(self.dac_read_subindex);
-        for rgb in &self.dac {
-            out.extend_from_slice(&[rgb.r, rgb.g, rgb.b]);
+        out.push(self.dac.pel_mask);
+        out.push(self.dac.write_index);
+        out.push(self.dac.write_subindex);
+        out.push(self.dac.read_index);
+        out.push(self.dac.read_subindex);
+        for rgb in &self.dac.palette {
+            out.extend_from_slice(&rgb.map(vga_6bit_to_8bit));
         }
 
         out.extend_from_slice(&self.vbe_index.to_le_bytes());
@@ -398,17 +395,16 @@ impl VgaDevice {
         self.attribute = snap.attribute;
         self.input_status1_vretrace = snap.input_status1_vretrace;
 
-        self.pel_mask = snap.pel_mask;
-        self.dac_write_index = snap.dac_write_index;
-        self.dac_write_subindex = snap.dac_write_subindex;
-        self.dac_read_index = snap.dac_read_index;
-        self.dac_read_subindex = snap.dac_read_subindex;
-        for (dst, src) in self.dac.iter_mut().zip(snap.dac.iter()) {
-            *dst = Rgb {
-                r: src[0],
-                g: src[1],
-                b: src[2],
-            };
+        self.dac.pel_mask = snap.pel_mask;
+        self.dac.restore_indices(
+            snap.dac_read_index,
+            snap.dac_read_subindex,
+            snap.dac_write_index,
+            snap.dac_write_subindex,
+            self.dac.write_latch,
+        );
+        for (dst, src) in self.dac.palette.iter_mut().zip(snap.dac.iter()) {
+            *dst = src.map(|c| c >> 2);
         }
 
         self.vbe_index = snap.vbe_index;
@@ -646,10 +642,7 @@ impl VgaSnapshotV2 {
 
 impl VgaDevice {
     pub fn snapshot_v2(&self) -> VgaSnapshotV2 {
-        let mut dac = [[0u8; 3]; 256];
-        for (dst, src) in dac.iter_mut().zip(self.dac.iter()) {
-            *dst = [src.r, src.g, src.b];
-        }
+        let dac = self.dac.palette.map(|rgb| rgb.map(vga_6bit_to_8bit));
 
         VgaSnapshotV2 {
             lfb_base: self.lfb_base(),
@@ -665,11 +658,11 @@ impl VgaDevice {
             attribute_flip_flop_data: self.attribute_flip_flop_data,
             attribute: self.attribute,
             input_status1_vretrace: self.input_status1_vretrace,
-            pel_mask: self.pel_mask,
-            dac_write_index: self.dac_write_index,
-            dac_write_subindex: self.dac_write_subindex,
-            dac_read_index: self.dac_read_index,
-            dac_read_subindex: self.dac_read_subindex,
+            pel_mask: self.dac.pel_mask,
+            dac_write_index: self.dac.write_index,
+            dac_write_subindex: self.dac.write_subindex,
+            dac_read_index: self.dac.read_index,
+            dac_read_subindex: self.dac.read_subindex,
             dac,
             vbe_index: self.vbe_index,
             vbe_xres: self.vbe.xres,
@@ -713,13 +706,13 @@ impl VgaDevice {
         out.extend_from_slice(&self.attribute);
         out.push(self.input_status1_vretrace as u8);
 
-        out.push(self.pel_mask);
-        out.push(self.dac_write_index);
-        out.push(self.dac_write_subindex);
-        out.push(self.dac_read_index);
-        out.push(self.dac_read_subindex);
-        for rgb in &self.dac {
-            out.extend_from_slice(&[rgb.r, rgb.g, rgb.b]);
+        out.push(self.dac.pel_mask);
+        out.push(self.dac.write_index);
+        out.push(self.dac.write_subindex);
+        out.push(self.dac.read_index);
+        out.push(self.dac.read_subindex);
+        for rgb in &self.dac.palette {
+            out.extend_from_slice(&rgb.map(vga_6bit_to_8bit));
         }
 
         out.extend_from_slice(&self.vbe_index.to_le_bytes());
@@ -758,17 +751,16 @@ impl VgaDevice {
         self.attribute = snap.attribute;
         self.input_status1_vretrace = snap.input_status1_vretrace;
 
-        self.pel_mask = snap.pel_mask;
-        self.dac_write_index = snap.dac_write_index;
-        self.dac_write_subindex = snap.dac_write_subindex;
-        self.dac_read_index = snap.dac_read_index;
-        self.dac_read_subindex = snap.dac_read_subindex;
-        for (dst, src) in self.dac.iter_mut().zip(snap.dac.iter()) {
-            *dst = Rgb {
-                r: src[0],
-                g: src[1],
-                b: src[2],
-            };
+        self.dac.pel_mask = snap.pel_mask;
+        self.dac.restore_indices(
+            snap.dac_read_index,
+            snap.dac_read_subindex,
+            snap.dac_write_index,
+            snap.dac_write_subindex,
+            self.dac.write_latch,
+        );
+        for (dst, src) in self.dac.palette.iter_mut().zip(snap.dac.iter()) {
+            *dst = src.map(|c| c >> 2);
         }
 
         self.vbe_index = snap.vbe_index;
@@ -0,0 +1,235 @@
+//! Shared VGA alphanumeric (text mode) renderer.
+//!
+//! [`VgaDevice`](crate::VgaDevice) and hosts that render the legacy text buffer themselves (such as
+//! the AeroGPU BIOS text scanout) both go through [`TextFrame::render`], so they agree on:
+//! - 8/9-dot character clocking (Sequencer Clocking Mode bit 0) and the 9th-column replication
+//!   rule for the line-graphics range `0xC0..=0xDF` (Attribute Mode Control bit 2).
+//! - Blink vs. background intensity (Attribute Mode Control bit 3).
+//! - The monochrome underline attribute (Attribute Mode Control bit 1, CRTC Underline Location).
+//! - The hardware cursor shape (CRTC Cursor Start/End scanlines, start bit 5 = disable).
+//!
+//! Blinking is driven by a deterministic guest-time epoch (see [`text_blink_epoch`]) rather than
+//! host time so that rendering is reproducible across runs and snapshots.
+
+use crate::text_font::FONT8X8_CP437;
+
+/// Character cell height in scanlines (the built-in 8x8 font is doubled vertically).
+pub const TEXT_CHAR_HEIGHT: usize = 16;
+
+/// Half-period of the hardware cursor blink.
+///
+/// Real VGA hardware toggles the cursor every 8 vertical retraces (~114ms at 70Hz); this is rounded
+/// to 100ms so blink epochs are a whole number of guest milliseconds.
+pub const TEXT_CURSOR_BLINK_HALF_PERIOD_NS: u64 = 100_000_000;
+
+/// Half-period of character blink (attribute bit 7 when blink is enabled).
+///
+/// Character blink runs at half the cursor rate, like the VGA's vsync/32 divider. One second of
+/// guest time is an odd number of half-periods, so two presents a second apart always observe
+/// opposite phases.
+pub const TEXT_BLINK_HALF_PERIOD_NS: u64 = 2 * TEXT_CURSOR_BLINK_HALF_PERIOD_NS;
+
+// Attribute Controller Mode Control register (index 0x10) bits.
+const MODE_CONTROL_MONO_EMULATION: u8 = 1 << 1;
+const MODE_CONTROL_LINE_GRAPHICS: u8 = 1 << 2;
+const MODE_CONTROL_BLINK: u8 = 1 << 3;
+
+/// Character cell width selected by the Sequencer Clocking Mode register (index 0x01).
+///
+/// Bit 0 (`8/9 Dot Clocks`) selects 8-dot characters when set and 9-dot characters when clear.
+pub fn text_char_width(clocking_mode: u8) -> usize {
+    if (clocking_mode & 0x01) != 0 {
+        8
+    } else {
+        9
+    }
+}
+
+/// Blink epoch for a guest timestamp: the number of elapsed cursor blink half-periods.
+pub fn text_blink_epoch(guest_time_ns: u64) -> u64 {
+    guest_time_ns / TEXT_CURSOR_BLINK_HALF_PERIOD_NS
+}
+
+/// Visibility of blinking elements for a given blink epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextBlinkPhase {
+    /// Whether characters with the blink attribute show their foreground.
+    pub chars_visible: bool,
+    /// Whether the hardware cursor is drawn.
+    pub cursor_visible: bool,
+}
+
+impl TextBlinkPhase {
+    pub fn from_epoch(epoch: u64) -> Self {
+        Self {
+            chars_visible: (epoch & 0x02) == 0,
+            cursor_visible: (epoch & 0x01) == 0,
+        }
+    }
+}
+
+/// Hardware cursor placement and shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextCursor {
+    pub row: usize,
+    pub col: usize,
+    /// CRTC Cursor Start (index 0x0A): bits 4-0 = first scanline, bit 5 = cursor disable.
+    pub start: u8,
+    /// CRTC Cursor End (index 0x0B): bits 4-0 = last scanline.
+    pub end: u8,
+}
+
+impl TextCursor {
+    fn scanlines(&self) -> Option<(usize, usize)> {
+        if (self.start & 0x20) != 0 {
+            return None;
+        }
+        let start = usize::from(self.start & 0x1F);
+        let end = usize::from(self.end & 0x1F).min(TEXT_CHAR_HEIGHT - 1);
+        // Like VGA hardware, a start scanline past the end scanline (or past the cell) hides the
+        // cursor instead of wrapping.
+        (start <= end).then_some((start, end))
+    }
+}
+
+/// Geometry and register state for one text frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFrame {
+    pub cols: usize,
+    pub rows: usize,
+    /// Character cell width in pixels (8 or 9, see [`text_char_width`]).
+    pub char_width: usize,
+    /// Attribute Controller Mode Control register (index 0x10).
+    pub mode_control: u8,
+    /// CRTC Underline Location register (index 0x14); only used in monochrome emulation.
+    pub underline_location: u8,
+    pub cursor: Option<TextCursor>,
+    /// Blink epoch to render at (see [`text_blink_epoch`]).
+    pub blink_epoch: u64,
+}
+
+impl TextFrame {
+    pub fn width(&self) -> usize {
+        self.cols * self.char_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows * TEXT_CHAR_HEIGHT
+    }
+
+    /// Render the frame into `fb` (`width() * height()` pixels).
+    ///
+    /// `cell(row, col)` returns the `(character, attribute)` pair for a cell and `color(index)`
+    /// maps a 4-bit attribute color index to an RGBA pixel.
+    ///
+    /// Returns whether the output depends on the blink phase (a blinking character or a visible
+    /// cursor is on screen), i.e. whether it must be re-rendered when the blink epoch changes.
+    pub fn render(
+        &self,
+        fb: &mut [u32],
+        mut cell: impl FnMut(usize, usize) -> (u8, u8),
+        mut color: impl FnMut(u8) -> u32,
+    ) -> bool {
+        let width = self.width();
+        debug_assert!(fb.len() >= width * self.height());
+
+        let line_graphics = (self.mode_control & MODE_CONTROL_LINE_GRAPHICS) != 0;
+        let blink_enabled = (self.mode_control & MODE_CONTROL_BLINK) != 0;
+        let mono = (self.mode_control & MODE_CONTROL_MONO_EMULATION) != 0;
+        let underline_row = usize::from(self.underline_location & 0x1F);
+        let phase = TextBlinkPhase::from_epoch(self.blink_epoch);
+        let mut blink_sensitive = false;
+
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let (ch, attr) = cell(row, col);
+
+                let fg = color(attr & 0x0F);
+                // Text background uses only 3 bits when blink is enabled (bit 7 becomes blink).
+                let bg = color(if blink_enabled {
+                    (attr >> 4) & 0x07
+                } else {
+                    (attr >> 4) & 0x0F
+                });
+
+                let blinking = blink_enabled && (attr & 0x80) != 0;
+                blink_sensitive |= blinking;
+                let glyph_visible = !blinking || phase.chars_visible;
+                // MDA-compatible underline: foreground 1 on background 0.
+                let underline = mono && (attr & 0x77) == 0x01;
+                let is_line_graphics = line_graphics && (0xC0..=0xDF).contains(&ch);
+
+                let x0 = col * self.char_width;
+                for y in 0..TEXT_CHAR_HEIGHT {
+                    let (bits, ninth) = if !glyph_visible {
+                        (0, false)
+                    } else if underline && y == underline_row {
+                        (0xFF, true)
+                    } else {
+                        let bits = FONT8X8_CP437[ch as usize][y / 2];
+                        (bits, is_line_graphics && (bits & 0x01) != 0)
+                    };
+
+                    let dst =
+                        &mut fb[(row * TEXT_CHAR_HEIGHT + y) * width + x0..][..self.char_width];
+                    for (x, px) in dst.iter_mut().enumerate() {
+                        let on = if x < 8 {
+                            ((bits >> (7 - x)) & 1) != 0
+                        } else {
+                            ninth
+                        };
+                        *px = if on { fg } else { bg };
+                    }
+                }
+
+                let Some(cursor) = self.cursor.filter(|c| c.row == row && c.col == col) else {
+                    continue;
+                };
+                let Some((start, end)) = cursor.scanlines() else {
+                    continue;
+                };
+                blink_sensitive = true;
+                if !phase.cursor_visible {
+                    continue;
+                }
+                for y in start..=end {
+                    let dst =
+                        &mut fb[(row * TEXT_CHAR_HEIGHT + y) * width + x0..][..self.char_width];
+                    for px in dst {
+                        *px = if *px == fg { bg } else { fg };
+                    }
+                }
+            }
+        }
+
+        blink_sensitive
+    }
+}
+
+/// Map a 4-bit text/planar color index through the Attribute Controller palette.
+///
+/// `attribute` is the Attribute Controller register file (at least indices `0x00..=0x14`). The
+/// result is an 8-bit DAC index before the PEL mask is applied.
+pub fn attribute_palette_lookup(color: u8, attribute: &[u8]) -> u8 {
+    // Attribute Controller indices.
+    const MODE_CONTROL: usize = 0x10;
+    const COLOR_PLANE_ENABLE: usize = 0x12;
+    const COLOR_SELECT: usize = 0x14;
+
+    // Mirror the VGA Attribute Controller palette mapping logic:
+    // - Color Plane Enable masks the 4-bit color index.
+    // - Palette registers provide a 6-bit "PEL" (0..=63).
+    // - When the Mode Control P54S bit is set, palette bits 5-4 are sourced from
+    //   Color Select bits 3-2 instead of the palette register.
+    // - The top 2 bits of the DAC index (7-6) come from Color Select bits 1-0.
+    let mode_control = attribute[MODE_CONTROL];
+    let color_plane_enable = attribute[COLOR_PLANE_ENABLE] & 0x0F;
+    let color_select = attribute[COLOR_SELECT];
+
+    let masked = (color & 0x0F) & color_plane_enable;
+    let mut pel = attribute[masked as usize] & 0x3F;
+    if (mode_control & 0x80) != 0 {
+        pel = (pel & 0x0F) | ((color_select & 0x0C) << 2);
+    }
+    ((color_select & 0x03) << 6) | pel
+}
@@ -8,6 +8,11 @@ use std::sync::Arc;
 pub const IOAPIC_MMIO_BASE: u64 = 0xFEC0_0000;
 pub const IOAPIC_MMIO_SIZE: u64 = 0x1000;
 
+/// MMIO base of the optional second IOAPIC (directly above the first one).
+pub const SECOND_IOAPIC_MMIO_BASE: u64 = IOAPIC_MMIO_BASE + IOAPIC_MMIO_SIZE;
+/// First GSI served by the optional second IOAPIC.
+pub const SECOND_IOAPIC_GSI_BASE: u32 = IoApic::NUM_REDIRECTION_ENTRIES as u32;
+
 const MAX_SNAPSHOT_REDIRECTION_ENTRIES: usize = 4096;
 
 /// I/O APIC ID (4-bit field in the ID register).
@@ -441,6 +441,17 @@ impl LocalApic {
         self.state.lock().unwrap().poll_timer(now);
     }
 
+    /// Nanoseconds until the timer next injects its vector, or `None` if the timer is stopped,
+    /// masked or the LAPIC is software-disabled.
+    pub fn ns_until_timer_interrupt(&self) -> Option<u64> {
+        let now = self.clock.now_ns();
+        let state = self.state.lock().unwrap();
+        if !state.enabled() || state.timer_masked() || state.initial_count == 0 {
+            return None;
+        }
+        Some(state.next_timer_deadline_ns?.saturating_sub(now))
+    }
+
     /// Injects a fixed interrupt vector into the LAPIC IRR.
     ///
     /// If the LAPIC is disabled (`SVR[8] == 0`), the interrupt is dropped.
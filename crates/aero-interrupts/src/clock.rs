@@ -38,3 +38,44 @@ impl Clock for NullClock {
         0
     }
 }
+
+/// Catch-up policy for periodic timers after a large guest time step (e.g. a host that was paused
+/// for minutes and then advances time in one tick).
+///
+/// When a single tick covers more than [`Self::max_missed_periods`] periods, a timer delivers at
+/// most [`Self::max_burst`] interrupts for it and drops the rest, while its counters still advance
+/// by the full elapsed time (as if the dropped interrupts had been delivered). Ticks covering fewer
+/// periods deliver every period. Without a policy ("strict" mode, for replay and tests) every
+/// elapsed period is delivered.
+///
+/// Timers that latch their interrupt (RTC `PF`, HPET status bits, the LAPIC IRR) already fold any
+/// number of missed periods into one pending interrupt per tick and realign to their period grid,
+/// so they satisfy the bound by construction; the policy matters for pulse-per-period sources like
+/// PIT channel 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerCatchupPolicy {
+    /// Periods a single tick may cover before it is coalesced.
+    pub max_missed_periods: u64,
+    /// Interrupts delivered for a coalesced tick.
+    pub max_burst: u64,
+}
+
+impl TimerCatchupPolicy {
+    /// Number of interrupts to deliver for a tick that covered `due` periods.
+    pub fn deliverable(&self, due: u64) -> u64 {
+        if due > self.max_missed_periods {
+            due.min(self.max_burst)
+        } else {
+            due
+        }
+    }
+}
+
+impl Default for TimerCatchupPolicy {
+    fn default() -> Self {
+        Self {
+            max_missed_periods: 100,
+            max_burst: 2,
+        }
+    }
+}
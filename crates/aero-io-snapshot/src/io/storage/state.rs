@@ -1117,6 +1117,18 @@ pub struct NvmeInFlightCommandState {
     pub length: u32,
 }
 
+/// A completion held back by a storage fault injector until guest time reaches `due_ns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvmeDeferredCompletionState {
+    pub due_ns: u64,
+    pub sqid: u16,
+    pub cqid: u16,
+    pub cid: u16,
+    /// Completion status field without the phase bit.
+    pub status: u16,
+    pub result: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct NvmeControllerState {
     pub cap: u64,
@@ -1145,11 +1157,14 @@ pub struct NvmeControllerState {
     pub io_cqs: Vec<NvmeCompletionQueueState>,
     pub intx_level: bool,
     pub in_flight: Vec<NvmeInFlightCommandState>,
+    /// Guest time used to schedule injected completion delays.
+    pub now_ns: u64,
+    pub deferred_completions: Vec<NvmeDeferredCompletionState>,
 }
 
 impl IoSnapshot for NvmeControllerState {
     const DEVICE_ID: [u8; 4] = *b"NVME";
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 2);
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 3);
 
     fn save_state(&self) -> Vec<u8> {
         const TAG_REGS: u16 = 1;
@@ -1164,6 +1179,8 @@ impl IoSnapshot for NvmeControllerState {
         const TAG_IO_CQS: u16 = 8;
         const TAG_INTX_LEVEL: u16 = 9;
         const TAG_FEATURES: u16 = 10;
+        // Fault-injector deferred completions (NVME 1.3+).
+        const TAG_DEFERRED_COMPLETIONS: u16 = 11;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
         let regs = Encoder::new()
@@ -1293,6 +1310,20 @@ impl IoSnapshot for NvmeControllerState {
         }
         w.field_bytes(TAG_IN_FLIGHT, inflight.finish());
 
+        let mut deferred = Encoder::new()
+            .u64(self.now_ns)
+            .u32(self.deferred_completions.len() as u32);
+        for c in &self.deferred_completions {
+            deferred = deferred
+                .u64(c.due_ns)
+                .u16(c.sqid)
+                .u16(c.cqid)
+                .u16(c.cid)
+                .u16(c.status)
+                .u32(c.result);
+        }
+        w.field_bytes(TAG_DEFERRED_COMPLETIONS, deferred.finish());
+
         w.finish()
     }
 
@@ -1307,6 +1338,7 @@ impl IoSnapshot for NvmeControllerState {
         const TAG_IO_CQS: u16 = 8;
         const TAG_INTX_LEVEL: u16 = 9;
         const TAG_FEATURES: u16 = 10;
+        const TAG_DEFERRED_COMPLETIONS: u16 = 11;
 
         const MAX_IO_QUEUES: usize = 4096;
         const MAX_IN_FLIGHT_COMMANDS: usize = 262_144;
@@ -1533,6 +1565,34 @@ impl IoSnapshot for NvmeControllerState {
             d.finish()?;
         }
 
+        // Deferred completions (NVME 1.3+). Older snapshots have none.
+        self.now_ns = 0;
+        self.deferred_completions.clear();
+        if let Some(buf) = r.bytes(TAG_DEFERRED_COMPLETIONS) {
+            let mut d = Decoder::new(buf);
+            self.now_ns = d.u64()?;
+            let count = d.u32()? as usize;
+            if count > MAX_IN_FLIGHT_COMMANDS {
+                return Err(SnapshotError::InvalidFieldEncoding(
+                    "nvme deferred completion count",
+                ));
+            }
+            self.deferred_completions
+                .try_reserve_exact(count)
+                .map_err(|_| SnapshotError::OutOfMemory)?;
+            for _ in 0..count {
+                self.deferred_completions.push(NvmeDeferredCompletionState {
+                    due_ns: d.u64()?,
+                    sqid: d.u16()?,
+                    cqid: d.u16()?,
+                    cid: d.u16()?,
+                    status: d.u16()?,
+                    result: d.u32()?,
+                });
+            }
+            d.finish()?;
+        }
+
         fn validate_sq(sq: &NvmeSubmissionQueueState) -> SnapshotResult<()> {
             if sq.size == 0 {
                 return Err(SnapshotError::InvalidFieldEncoding("nvme sq size"));
@@ -13,8 +13,9 @@ use aero_io_snapshot::io::storage::state::{
     IdeBusMasterChannelState, IdeChannelState, IdeControllerState, IdeDataMode, IdeDmaDirection,
     IdeDmaRequestState, IdeDriveState, IdePioWriteState, IdePortMapState, IdeTaskFileState,
     IdeTransferKind, LocalDiskBackendKind, LocalDiskBackendState, NvmeCompletionQueueState,
-    NvmeControllerState, NvmeInFlightCommandState, NvmeSubmissionQueueState, PciConfigSpaceState,
-    RemoteDiskBackendState, RemoteDiskBaseState, RemoteDiskValidator,
+    NvmeControllerState, NvmeDeferredCompletionState, NvmeInFlightCommandState,
+    NvmeSubmissionQueueState, PciConfigSpaceState, RemoteDiskBackendState, RemoteDiskBaseState,
+    RemoteDiskValidator,
 };
 use aero_storage::SECTOR_SIZE;
 
@@ -397,6 +398,15 @@ fn nvme_state_roundtrip() {
             lba: 0xabcd,
             length: 4096,
         }],
+        now_ns: 1_000,
+        deferred_completions: vec![NvmeDeferredCompletionState {
+            due_ns: 1_500,
+            sqid: 1,
+            cqid: 1,
+            cid: 9,
+            status: 0x4004,
+            result: 0x1234,
+        }],
     };
 
     let snap = nvme.save_state();
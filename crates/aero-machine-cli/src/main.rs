@@ -575,7 +575,7 @@ mod native {
                 } else {
                     eprintln!("guest requested reset: {kind:?} (continuing)");
                 }
-                machine.reset();
+                machine.reset_with_kind(kind);
                 Ok(LoopControl::Continue)
             }
             RunExit::Assist { reason, .. } => {
@@ -585,11 +585,32 @@ mod native {
                 bail!("execution stopped: exception: {exception:?}")
             }
             RunExit::CpuExit { exit, .. } => bail!("execution stopped: cpu exit: {exit:?}"),
+            RunExit::HangSuspected { details, .. } => {
+                bail!("execution stopped: guest hang suspected: {details:?}")
+            }
+            RunExit::PowerOff { .. } => {
+                eprintln!("guest powered off after {total_executed} instructions");
+                Ok(LoopControl::Break)
+            }
+            RunExit::SmiUnsupported { .. } => {
+                eprintln!("guest SMI dropped (SMM is not emulated; continuing)");
+                Ok(LoopControl::Continue)
+            }
+            RunExit::UnknownPortIo {
+                port, size, write, ..
+            } => {
+                bail!("execution stopped: unknown port access: port={port:#06x} size={size} write={write}")
+            }
+            RunExit::UnknownMmio {
+                paddr, size, write, ..
+            } => {
+                bail!("execution stopped: unknown MMIO access: paddr={paddr:#x} size={size} write={write}")
+            }
         }
     }
 
     fn dump_vga_png(machine: &mut Machine, path: &Path) -> Result<()> {
-        machine.display_present();
+        machine.display_present(true);
         let (w, h) = machine.display_resolution();
         if w == 0 || h == 0 {
             bail!("no VGA framebuffer available (resolution was {w}x{h})");
@@ -151,6 +151,7 @@ fn boots_aerospar_disk_image() {
         AeroSparseConfig {
             disk_size_bytes: bytes.len() as u64,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .expect("failed to create aerospar disk");
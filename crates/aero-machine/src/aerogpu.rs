@@ -1,9 +1,10 @@
 #![forbid(unsafe_code)]
 
 use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
 
 use aero_devices::clock::{Clock as _, ManualClock};
-use aero_devices::pci::{PciBarMmioHandler, PciConfigSpace, PciDevice};
+use aero_devices::pci::{MsixCapability, PciBarMmioHandler, PciConfigSpace, PciDevice};
 use aero_devices_gpu::backend::{
     AeroGpuBackendScanout, AeroGpuBackendSubmission, AeroGpuCommandBackend,
 };
@@ -17,6 +18,7 @@ use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
 use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
+use aero_platform::interrupts::msi::MsiTrigger;
 use aero_protocol::aerogpu::aerogpu_cmd::{
     cmd_stream_has_vsync_present_bytes, cmd_stream_has_vsync_present_reader,
     decode_cmd_stream_header_le, AerogpuCmdStreamHeader as ProtocolCmdStreamHeader,
@@ -25,6 +27,10 @@ use aero_protocol::aerogpu::aerogpu_pci as pci;
 use aero_protocol::aerogpu::aerogpu_ring as ring;
 use memory::MemoryBus;
 
+use crate::aerogpu_validation::{
+    validate_submission, AeroGpuValidationFailure, AEROGPU_VALIDATION_FAILURE_CAP,
+};
+use crate::machine_events::{MachineEventKind, MachineEventLog};
 use crate::AerogpuSubmission;
 
 #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
@@ -39,9 +45,9 @@ use aero_shared::scanout_state::{
 // Keep a tighter limit on wasm32: browser/test environments can have smaller heaps, and extremely
 // large command streams are not expected there.
 #[cfg(target_arch = "wasm32")]
-const MAX_CMD_STREAM_SIZE_BYTES: u32 = 16 * 1024 * 1024;
+pub(crate) const MAX_CMD_STREAM_SIZE_BYTES: u32 = 16 * 1024 * 1024;
 #[cfg(not(target_arch = "wasm32"))]
-const MAX_CMD_STREAM_SIZE_BYTES: u32 = 64 * 1024 * 1024;
+pub(crate) const MAX_CMD_STREAM_SIZE_BYTES: u32 = 64 * 1024 * 1024;
 // -----------------------------------------------------------------------------
 // Defensive caps (host readback paths)
 // -----------------------------------------------------------------------------
@@ -66,9 +72,9 @@ const MAX_HOST_CURSOR_RGBA8888_BYTES: usize = 4 * 1024 * 1024; // 1,048,576 pixe
 // Keep tighter limits on wasm32 where heaps are typically smaller and extremely large allocation
 // tables are not expected.
 #[cfg(target_arch = "wasm32")]
-const MAX_AEROGPU_ALLOC_TABLE_BYTES: u32 = 4 * 1024 * 1024;
+pub(crate) const MAX_AEROGPU_ALLOC_TABLE_BYTES: u32 = 4 * 1024 * 1024;
 #[cfg(not(target_arch = "wasm32"))]
-const MAX_AEROGPU_ALLOC_TABLE_BYTES: u32 = 16 * 1024 * 1024;
+pub(crate) const MAX_AEROGPU_ALLOC_TABLE_BYTES: u32 = 16 * 1024 * 1024;
 const MAX_PENDING_AEROGPU_SUBMISSIONS: usize = 256;
 // Total memory cap (in bytes) for queued `AerogpuSubmission` payloads.
 //
@@ -82,12 +88,19 @@ const MAX_PENDING_AEROGPU_SUBMISSIONS_BYTES: usize = 4 * 1024;
 #[cfg(not(test))]
 const MAX_PENDING_AEROGPU_SUBMISSIONS_BYTES: usize = 128 * 1024 * 1024;
 
+/// Lowest vblank rate accepted by [`crate::Machine::aerogpu_set_vblank_rate`] (10Hz).
+pub const AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ: u32 = 10_000;
+/// Highest vblank rate accepted by [`crate::Machine::aerogpu_set_vblank_rate`] (240Hz).
+pub const AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ: u32 = 240_000;
+
 fn supported_features() -> u64 {
     pci::AEROGPU_FEATURE_FENCE_PAGE
         | pci::AEROGPU_FEATURE_CURSOR
         | pci::AEROGPU_FEATURE_SCANOUT
         | pci::AEROGPU_FEATURE_VBLANK
         | pci::AEROGPU_FEATURE_ERROR_INFO
+        | pci::AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE
+        | pci::AEROGPU_FEATURE_MSIX
         // Transfer/copy commands (and optional guest writeback) are feature-gated starting at
         // ABI 1.1+.
         //
@@ -706,6 +719,11 @@ pub struct AeroGpuMmioDevice {
 
     irq_status: u32,
     irq_enable: u32,
+    /// MSI sink used when the guest enables MSI-X (host wiring; survives [`Self::reset`]).
+    ///
+    /// The MSI-X table and PBA live in the internal `config` image's capability and are exposed
+    /// through BAR0 (`AEROGPU_PCI_BAR0_MSIX_{TABLE,PBA}_OFFSET_BYTES`).
+    msi_target: Option<Box<dyn MsiTrigger>>,
 
     // ---------------------------------------------------------------------
     // Error reporting (ABI 1.3+)
@@ -787,6 +805,17 @@ pub struct AeroGpuMmioDevice {
     /// must defer those DMA updates until bus mastering is enabled again (mirroring how doorbells
     /// are deferred).
     ring_reset_pending_dma: bool,
+
+    // ---------------------------------------------------------------------
+    // Strict submission validation (host debug option; see `aerogpu_validation`).
+    // ---------------------------------------------------------------------
+    strict_validation: bool,
+    /// Highest non-zero `signal_fence` accepted in strict mode since the last ring reset.
+    strict_last_fence: u64,
+    validation_failures: VecDeque<AeroGpuValidationFailure>,
+
+    /// Machine event stream receiving latched errors (host wiring; survives [`Self::reset`]).
+    event_log: Option<Rc<MachineEventLog>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -873,6 +902,7 @@ impl Default for AeroGpuMmioDevice {
 
             irq_status: 0,
             irq_enable: 0,
+            msi_target: None,
             error_code: pci::AerogpuErrorCode::None as u32,
             error_fence: 0,
             error_count: 0,
@@ -920,6 +950,12 @@ impl Default for AeroGpuMmioDevice {
             doorbell_pending: false,
             ring_reset_pending: false,
             ring_reset_pending_dma: false,
+
+            strict_validation: false,
+            strict_last_fence: 0,
+            validation_failures: VecDeque::new(),
+
+            event_log: None,
         }
     }
 }
@@ -947,6 +983,10 @@ impl AeroGpuMmioDevice {
         self.backend_completed_fences.insert(fence);
     }
 
+    pub(crate) fn submission_bridge_enabled(&self) -> bool {
+        self.submission_bridge_enabled
+    }
+
     pub(crate) fn enable_submission_bridge(&mut self) {
         // The submission bridge routes execution out-of-process (e.g. browser GPU worker). Ensure
         // we are not simultaneously using an in-process command backend.
@@ -1149,6 +1189,12 @@ impl AeroGpuMmioDevice {
         let abi_version = self.abi_version;
         let clock = self.clock.clone();
         let submission_bridge_enabled = self.submission_bridge_enabled;
+        // The vblank rate is host configuration (see `set_vblank_period_ns`), not guest state.
+        let vblank_interval_ns = self.vblank_interval_ns;
+        let scanout0_vblank_period_ns = self.scanout0_vblank_period_ns;
+        let strict_validation = self.strict_validation;
+        let msi_target = self.msi_target.take();
+        let event_log = self.event_log.take();
         let mut backend = self.backend.take();
         if let Some(backend) = backend.as_mut() {
             backend.reset();
@@ -1157,17 +1203,152 @@ impl AeroGpuMmioDevice {
             supported_features,
             abi_version,
             clock,
+            vblank_interval_ns,
+            scanout0_vblank_period_ns,
             submission_bridge_enabled,
+            strict_validation,
+            msi_target,
             backend,
+            event_log,
             ..Default::default()
         };
     }
 
+    /// Attach or detach the MSI sink used to deliver interrupts when the guest enables MSI-X.
+    pub fn set_msi_target(&mut self, target: Option<Box<dyn MsiTrigger>>) {
+        self.msi_target = target;
+    }
+
+    /// Whether interrupts are currently signaled via MSI-X instead of legacy INTx.
+    fn msix_active(&self) -> bool {
+        self.msi_target.is_some()
+            && self
+                .config
+                .capability::<MsixCapability>()
+                .is_some_and(|msix| msix.enabled())
+    }
+
+    /// Send the MSI-X message for each newly latched, enabled IRQ cause in `bits`.
+    ///
+    /// MSI-X is edge-triggered: each event raises its vector once (or sets its PBA bit while
+    /// masked). `IRQ_STATUS` still latches the cause so the driver's ISR/ACK flow is unchanged.
+    fn signal_msix(&mut self, bits: u32) {
+        if !self.msix_active() {
+            return;
+        }
+        let bits = bits & self.irq_enable;
+        let vectors = [
+            (pci::AEROGPU_IRQ_FENCE, pci::AEROGPU_MSIX_VECTOR_FENCE),
+            (
+                pci::AEROGPU_IRQ_SCANOUT_VBLANK | pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED,
+                pci::AEROGPU_MSIX_VECTOR_VBLANK,
+            ),
+            (pci::AEROGPU_IRQ_ERROR, pci::AEROGPU_MSIX_VECTOR_ERROR),
+        ];
+        let (Some(target), Some(msix)) = (
+            self.msi_target.as_mut(),
+            self.config.capability_mut::<MsixCapability>(),
+        ) else {
+            return;
+        };
+        for (mask, vector) in vectors {
+            if (bits & mask) != 0 {
+                msix.trigger_into(vector as u16, target.as_mut());
+            }
+        }
+    }
+
+    /// Re-drive MSI-X vectors left pending in the PBA once they become deliverable (entry or
+    /// function unmasked).
+    pub(crate) fn deliver_pending_msix(&mut self) {
+        if let (Some(target), Some(msix)) = (
+            self.msi_target.as_mut(),
+            self.config.capability_mut::<MsixCapability>(),
+        ) {
+            msix.deliver_pending_into(target.as_mut());
+        }
+    }
+
+    /// MSI-X table and PBA bytes, for the machine snapshot.
+    pub(crate) fn msix_snapshot_state(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let msix = self.config.capability::<MsixCapability>()?;
+        let mut pba = Vec::with_capacity(msix.snapshot_pba().len().saturating_mul(8));
+        for word in msix.snapshot_pba() {
+            pba.extend_from_slice(&word.to_le_bytes());
+        }
+        Some((msix.snapshot_table().to_vec(), pba))
+    }
+
+    pub(crate) fn restore_msix_state(&mut self, table: &[u8], pba: &[u8]) -> SnapshotResult<()> {
+        let Some(msix) = self.config.capability_mut::<MsixCapability>() else {
+            return Err(SnapshotError::InvalidFieldEncoding(
+                "snapshot contains MSI-X state but device has no MSI-X capability",
+            ));
+        };
+        msix.restore_table(table)?;
+        msix.restore_pba_bytes(pba)
+    }
+
+    /// Enable or disable strict validation of ring submissions (host debug option).
+    ///
+    /// In strict mode a malformed submission latches a class-specific `AEROGPU_ERROR_*` code,
+    /// raises `AEROGPU_IRQ_ERROR` and is consumed without executing or completing its fence. The
+    /// setting is host configuration: it survives [`Self::reset`] and is not snapshotted.
+    pub fn set_strict_validation(&mut self, enabled: bool) {
+        self.strict_validation = enabled;
+    }
+
+    pub fn strict_validation(&self) -> bool {
+        self.strict_validation
+    }
+
+    /// Drain the submissions rejected by strict validation (oldest first).
+    pub fn take_validation_failures(&mut self) -> Vec<AeroGpuValidationFailure> {
+        self.validation_failures.drain(..).collect()
+    }
+
+    /// Returns `false` (after latching the error) if the submission must not be executed.
+    fn validate_submission_strict(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        ring_index: u32,
+        desc: &ring::AerogpuSubmitDesc,
+    ) -> bool {
+        match validate_submission(mem, desc, self.strict_last_fence) {
+            Ok(()) => {
+                self.strict_last_fence = self.strict_last_fence.max(desc.signal_fence);
+                true
+            }
+            Err(violation) => {
+                self.record_error(violation.code(), desc.signal_fence);
+                if self.validation_failures.len() == AEROGPU_VALIDATION_FAILURE_CAP {
+                    self.validation_failures.pop_front();
+                }
+                self.validation_failures
+                    .push_back(AeroGpuValidationFailure::capture(
+                        mem, ring_index, desc, violation,
+                    ));
+                false
+            }
+        }
+    }
+
+    pub(crate) fn set_event_log(&mut self, event_log: Option<Rc<MachineEventLog>>) {
+        self.event_log = event_log;
+    }
+
     fn record_error(&mut self, code: pci::AerogpuErrorCode, fence: u64) {
+        if let Some(events) = &self.event_log {
+            events.record(MachineEventKind::AeroGpuError {
+                code: code as u32,
+                fence,
+            });
+        }
         self.error_code = code as u32;
         self.error_fence = fence;
         self.error_count = self.error_count.saturating_add(1);
         self.irq_status |= pci::AEROGPU_IRQ_ERROR;
+        self.signal_msix(pci::AEROGPU_IRQ_ERROR);
     }
 
     fn command(&self) -> u16 {
@@ -1193,6 +1374,8 @@ impl AeroGpuMmioDevice {
             pitch_bytes: 0,
             // Keep format at a stable default even while disabled.
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         }
     }
 
@@ -1271,6 +1454,8 @@ impl AeroGpuMmioDevice {
             height,
             pitch_bytes: self.scanout0_pitch_bytes,
             format,
+            slot0_offset: 0,
+            slot1_offset: 0,
         }
     }
 
@@ -1430,6 +1615,11 @@ impl AeroGpuMmioDevice {
     }
 
     pub fn irq_level(&self) -> bool {
+        // With MSI-X enabled (and an MSI sink attached) interrupts are message-signaled; legacy
+        // INTx stays deasserted.
+        if self.msix_active() {
+            return false;
+        }
         // Respect PCI COMMAND.INTX_DISABLE (bit 10).
         if self.intx_disabled() {
             return false;
@@ -1696,6 +1886,7 @@ impl AeroGpuMmioDevice {
             // interrupts when a guest re-enables vblank delivery.
             if (self.irq_enable & pci::AEROGPU_IRQ_SCANOUT_VBLANK) != 0 {
                 self.irq_status |= pci::AEROGPU_IRQ_SCANOUT_VBLANK;
+                self.signal_msix(pci::AEROGPU_IRQ_SCANOUT_VBLANK);
             }
 
             // Fence completion is gated by PCI COMMAND.BME: without bus mastering, the device must
@@ -1723,6 +1914,30 @@ impl AeroGpuMmioDevice {
         self.next_vblank_ns = Some(next);
     }
 
+    /// Change the vblank period at runtime (host refresh-rate governor).
+    ///
+    /// Vblank edges that already elapsed under the old period are delivered first, and the edge
+    /// that is already scheduled still fires at its original deadline; the new period applies to
+    /// every edge after it. This keeps `VBLANK_SEQ` monotonic and never drops or doubles a vblank
+    /// interrupt at the transition. If enabled in `IRQ_ENABLE`, the change latches
+    /// `AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED` so the guest driver re-reads
+    /// `SCANOUT0_VBLANK_PERIOD_NS`.
+    pub fn set_vblank_period_ns(&mut self, period_ns: u64) {
+        let period_ns = period_ns.max(1);
+        if self.vblank_interval_ns == Some(period_ns) {
+            return;
+        }
+
+        self.tick_vblank(self.now_ns);
+        self.vblank_interval_ns = Some(period_ns);
+        self.scanout0_vblank_period_ns = period_ns_to_reg(period_ns);
+
+        if (self.irq_enable & pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED) != 0 {
+            self.irq_status |= pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED;
+            self.signal_msix(pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED);
+        }
+    }
+
     pub fn tick(&mut self, delta_ns: u64, _mem: &mut dyn MemoryBus) {
         if delta_ns == 0 {
             return;
@@ -1730,6 +1945,16 @@ impl AeroGpuMmioDevice {
         self.tick_vblank(self.now_ns.saturating_add(delta_ns));
     }
 
+    /// Whether [`AeroGpuMmioDevice::tick`] would only advance the cached `now_ns`: scanout 0 is
+    /// disabled and no vblank edge or vblank IRQ is outstanding. With a platform clock attached,
+    /// every path that reads the time re-samples the clock, so such ticks can be skipped.
+    pub(crate) fn vblank_idle(&self) -> bool {
+        self.clock.is_some()
+            && !self.scanout0_enable
+            && self.next_vblank_ns.is_none()
+            && self.irq_status & pci::AEROGPU_IRQ_SCANOUT_VBLANK == 0
+    }
+
     pub(crate) fn cursor_snapshot(&self) -> AeroGpuCursorConfig {
         AeroGpuCursorConfig {
             enable: self.cursor_enable,
@@ -1786,6 +2011,7 @@ impl AeroGpuMmioDevice {
             self.error_code = pci::AerogpuErrorCode::None as u32;
             self.error_fence = 0;
             self.error_count = 0;
+            self.strict_last_fence = 0;
             self.pending_fence_completions.clear();
             self.backend_completed_fences.clear();
             self.pending_backend_fence_completions.clear();
@@ -1945,7 +2171,7 @@ impl AeroGpuMmioDevice {
                     let mut desc_buf = [0u8; ring::AerogpuSubmitDesc::SIZE_BYTES];
                     mem.read_physical(desc_gpa, &mut desc_buf);
                     if let Ok(desc) = ring::AerogpuSubmitDesc::decode_from_le_bytes(&desc_buf) {
-                        self.consume_submission(mem, &desc);
+                        self.consume_submission(mem, head, &desc);
                     } else {
                         self.record_error(pci::AerogpuErrorCode::CmdDecode, 0);
                     }
@@ -2019,7 +2245,16 @@ impl AeroGpuMmioDevice {
         self.write_fence_page_if_dirty(mem, true);
     }
 
-    fn consume_submission(&mut self, mem: &mut dyn MemoryBus, desc: &ring::AerogpuSubmitDesc) {
+    fn consume_submission(
+        &mut self,
+        mem: &mut dyn MemoryBus,
+        ring_index: u32,
+        desc: &ring::AerogpuSubmitDesc,
+    ) {
+        if self.strict_validation && !self.validate_submission_strict(mem, ring_index, desc) {
+            return;
+        }
+
         // Preserve upstream behaviour: invalid descriptors still count as consumed, but latch an
         // error payload if supported.
         let valid_desc = desc.validate_prefix().is_ok();
@@ -2310,6 +2545,7 @@ impl AeroGpuMmioDevice {
         // re-enabled.
         if wants_irq && (self.irq_enable & pci::AEROGPU_IRQ_FENCE) != 0 {
             self.irq_status |= pci::AEROGPU_IRQ_FENCE;
+            self.signal_msix(pci::AEROGPU_IRQ_FENCE);
         }
     }
 
@@ -2462,7 +2698,11 @@ impl AeroGpuMmioDevice {
                     self.tick_vblank(self.now_ns);
                 }
 
+                let newly_enabled = value & !self.irq_enable;
                 self.irq_enable = value;
+                // A still-latched cause (e.g. ERROR) asserts INTx as soon as it is unmasked; send
+                // the matching MSI-X message for the same transition.
+                self.signal_msix(self.irq_status & newly_enabled);
                 // Clear any IRQ status bits that are now masked so re-enabling doesn't immediately
                 // deliver a stale interrupt.
                 if (value & pci::AEROGPU_IRQ_FENCE) == 0 {
@@ -2471,6 +2711,9 @@ impl AeroGpuMmioDevice {
                 if (value & pci::AEROGPU_IRQ_SCANOUT_VBLANK) == 0 {
                     self.irq_status &= !pci::AEROGPU_IRQ_SCANOUT_VBLANK;
                 }
+                if (value & pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED) == 0 {
+                    self.irq_status &= !pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED;
+                }
             }
             x if x == pci::AEROGPU_MMIO_REG_IRQ_ACK as u64 => {
                 self.irq_status &= !value;
@@ -2675,8 +2918,71 @@ impl AeroGpuMmioDevice {
     }
 }
 
+impl AeroGpuMmioDevice {
+    /// Read from the MSI-X table/PBA if `offset` falls inside either window of BAR0.
+    fn msix_bar0_read(&mut self, offset: u64, size: usize) -> Option<u64> {
+        let msix = self.config.capability_mut::<MsixCapability>()?;
+        let mut data = [0u8; 8];
+        let size = size.clamp(1, 8);
+        if msix.table_bir() == pci::AEROGPU_PCI_BAR0_INDEX as u8 {
+            let base = u64::from(msix.table_offset());
+            let end = base.saturating_add(msix.table_len_bytes() as u64);
+            if offset >= base && offset < end {
+                msix.table_read(offset - base, &mut data[..size]);
+                return Some(u64::from_le_bytes(data));
+            }
+        }
+        if msix.pba_bir() == pci::AEROGPU_PCI_BAR0_INDEX as u8 {
+            let base = u64::from(msix.pba_offset());
+            let end = base.saturating_add(msix.pba_len_bytes() as u64);
+            if offset >= base && offset < end {
+                msix.pba_read(offset - base, &mut data[..size]);
+                return Some(u64::from_le_bytes(data));
+            }
+        }
+        None
+    }
+
+    /// Write to the MSI-X table/PBA if `offset` falls inside either window of BAR0. Returns
+    /// whether the access was consumed.
+    fn msix_bar0_write(&mut self, offset: u64, size: usize, value: u64) -> bool {
+        let Some(msix) = self.config.capability_mut::<MsixCapability>() else {
+            return false;
+        };
+        let size = size.clamp(1, 8);
+        let data = value.to_le_bytes();
+        if msix.table_bir() == pci::AEROGPU_PCI_BAR0_INDEX as u8 {
+            let base = u64::from(msix.table_offset());
+            let end = base.saturating_add(msix.table_len_bytes() as u64);
+            if offset >= base && offset < end {
+                msix.table_write(offset - base, &data[..size]);
+                // Unmasking an entry makes its pending vector deliverable.
+                if let Some(target) = self.msi_target.as_mut() {
+                    msix.deliver_pending_into(target.as_mut());
+                }
+                return true;
+            }
+        }
+        if msix.pba_bir() == pci::AEROGPU_PCI_BAR0_INDEX as u8 {
+            let base = u64::from(msix.pba_offset());
+            let end = base.saturating_add(msix.pba_len_bytes() as u64);
+            if offset >= base && offset < end {
+                msix.pba_write(offset - base, &data[..size]);
+                return true;
+            }
+        }
+        false
+    }
+}
+
 impl PciBarMmioHandler for AeroGpuMmioDevice {
     fn read(&mut self, offset: u64, size: usize) -> u64 {
+        if size != 0 {
+            // The MSI-X table/PBA share BAR0 with the register file; dispatch them first.
+            if let Some(value) = self.msix_bar0_read(offset, size) {
+                return value;
+            }
+        }
         match size {
             0 => 0,
             1 | 2 | 4 => {
@@ -2702,6 +3008,9 @@ impl PciBarMmioHandler for AeroGpuMmioDevice {
     }
 
     fn write(&mut self, offset: u64, size: usize, value: u64) {
+        if size != 0 && self.msix_bar0_write(offset, size, value) {
+            return;
+        }
         match size {
             0 => {}
             1 | 2 => {
@@ -4410,7 +4719,7 @@ mod tests {
 
 impl IoSnapshot for AeroGpuMmioDevice {
     const DEVICE_ID: [u8; 4] = *b"AGPU";
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 1);
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 2);
 
     fn save_state(&self) -> Vec<u8> {
         const TAG_ABI_VERSION: u16 = 1;
@@ -4451,6 +4760,8 @@ impl IoSnapshot for AeroGpuMmioDevice {
         const TAG_DOORBELL_PENDING: u16 = 32;
         const TAG_RING_RESET_PENDING: u16 = 33;
         const TAG_RING_RESET_PENDING_DMA: u16 = 35;
+        const TAG_MSIX_TABLE: u16 = 36;
+        const TAG_MSIX_PBA: u16 = 37;
 
         // Scanout dirty flag exists only when the shared scanout interface is enabled.
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
@@ -4504,6 +4815,11 @@ impl IoSnapshot for AeroGpuMmioDevice {
         w.field_bool(TAG_RING_RESET_PENDING, self.ring_reset_pending);
         w.field_bool(TAG_RING_RESET_PENDING_DMA, self.ring_reset_pending_dma);
 
+        if let Some((table, pba)) = self.msix_snapshot_state() {
+            w.field_bytes(TAG_MSIX_TABLE, table);
+            w.field_bytes(TAG_MSIX_PBA, pba);
+        }
+
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
         {
             w.field_bool(TAG_SCANOUT0_DIRTY, self.scanout0_dirty);
@@ -4551,6 +4867,8 @@ impl IoSnapshot for AeroGpuMmioDevice {
         const TAG_DOORBELL_PENDING: u16 = 32;
         const TAG_RING_RESET_PENDING: u16 = 33;
         const TAG_RING_RESET_PENDING_DMA: u16 = 35;
+        const TAG_MSIX_TABLE: u16 = 36;
+        const TAG_MSIX_PBA: u16 = 37;
 
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
         const TAG_SCANOUT0_DIRTY: u16 = 34;
@@ -4709,6 +5027,12 @@ impl IoSnapshot for AeroGpuMmioDevice {
         self.ring_reset_pending = ring_reset_pending;
         self.ring_reset_pending_dma = ring_reset_pending_dma;
 
+        // MSI-X table/PBA (1.2+). The enable/function-mask bits live in the canonical PCI config
+        // space and are re-synchronized by the platform.
+        if let (Some(table), Some(pba)) = (r.bytes(TAG_MSIX_TABLE), r.bytes(TAG_MSIX_PBA)) {
+            self.restore_msix_state(table, pba)?;
+        }
+
         // Defensive: if scanout or vblank pacing is disabled, do not leave a pending deadline.
         if self.vblank_interval_ns.is_none() || !self.scanout0_enable {
             self.next_vblank_ns = None;
@@ -0,0 +1,401 @@
+//! Strict AeroGPU submission validation (see
+//! [`crate::Machine::aerogpu_set_strict_validation`]).
+//!
+//! The default ring decoder is forgiving: malformed submissions are still forwarded and their
+//! fences complete, which keeps bring-up guests moving but lets bugs in a driver's stream encoder
+//! go unnoticed. In strict mode every submission is checked before it is captured or executed:
+//!
+//! - the submit descriptor prefix ([`aero_protocol::aerogpu::aerogpu_ring`]);
+//! - the `aerogpu_cmd_stream_header` (magic, ABI major, `size_bytes` against the descriptor);
+//! - every packet header: 4-byte aligned `size_bytes`, at least the packet's fixed layout, and no
+//!   overrun of the declared stream size;
+//! - the alloc table, and that every non-zero `backing_alloc_id` is present in it;
+//! - fence monotonicity: `signal_fence` must not be lower than an earlier submission's fence
+//!   (repeating the same value is allowed, as in permissive mode).
+//!
+//! A violation latches a class-specific `AEROGPU_ERROR_*` code in the BAR0 error registers,
+//! raises `AEROGPU_IRQ_ERROR`, consumes the submission without completing its fence, and records a
+//! bounded copy of it for the host ([`crate::Machine::aerogpu_take_validation_failures`]).
+
+use aero_protocol::aerogpu::aerogpu_cmd as cmd;
+use aero_protocol::aerogpu::aerogpu_pci::AerogpuErrorCode;
+use aero_protocol::aerogpu::aerogpu_ring as ring;
+use memory::MemoryBus;
+
+use crate::aerogpu::{MAX_AEROGPU_ALLOC_TABLE_BYTES, MAX_CMD_STREAM_SIZE_BYTES};
+
+/// Maximum number of failures retained between
+/// [`crate::Machine::aerogpu_take_validation_failures`] calls; the oldest are dropped first.
+pub const AEROGPU_VALIDATION_FAILURE_CAP: usize = 32;
+
+/// Maximum number of command stream (and alloc table) bytes copied into an
+/// [`AeroGpuValidationFailure`].
+pub const AEROGPU_VALIDATION_CAPTURE_BYTES: usize = 4096;
+
+/// A submission rejected by strict validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AeroGpuValidationFailure {
+    /// Code latched in `AEROGPU_MMIO_REG_ERROR_CODE`.
+    pub code: AerogpuErrorCode,
+    /// Human-readable description of the violation.
+    pub detail: String,
+    /// Unmasked ring index (`head` value) of the submission.
+    pub ring_index: u32,
+    pub flags: u32,
+    pub context_id: u32,
+    pub engine_id: u32,
+    pub signal_fence: u64,
+    pub cmd_gpa: u64,
+    pub cmd_size_bytes: u32,
+    pub alloc_table_gpa: u64,
+    pub alloc_table_size_bytes: u32,
+    /// Leading bytes of the command buffer (at most [`AEROGPU_VALIDATION_CAPTURE_BYTES`]).
+    pub cmd_stream: Vec<u8>,
+    /// Leading bytes of the alloc table (at most [`AEROGPU_VALIDATION_CAPTURE_BYTES`]).
+    pub alloc_table: Vec<u8>,
+}
+
+impl AeroGpuValidationFailure {
+    pub(crate) fn capture(
+        mem: &mut dyn MemoryBus,
+        ring_index: u32,
+        desc: &ring::AerogpuSubmitDesc,
+        violation: Violation,
+    ) -> Self {
+        Self {
+            code: violation.code,
+            detail: violation.detail,
+            ring_index,
+            flags: desc.flags,
+            context_id: desc.context_id,
+            engine_id: desc.engine_id,
+            signal_fence: desc.signal_fence,
+            cmd_gpa: desc.cmd_gpa,
+            cmd_size_bytes: desc.cmd_size_bytes,
+            alloc_table_gpa: desc.alloc_table_gpa,
+            alloc_table_size_bytes: desc.alloc_table_size_bytes,
+            cmd_stream: capture_prefix(mem, desc.cmd_gpa, desc.cmd_size_bytes),
+            alloc_table: capture_prefix(mem, desc.alloc_table_gpa, desc.alloc_table_size_bytes),
+        }
+    }
+}
+
+pub(crate) struct Violation {
+    code: AerogpuErrorCode,
+    detail: String,
+}
+
+impl Violation {
+    pub(crate) fn code(&self) -> AerogpuErrorCode {
+        self.code
+    }
+}
+
+fn violation(code: AerogpuErrorCode, detail: impl Into<String>) -> Violation {
+    Violation {
+        code,
+        detail: detail.into(),
+    }
+}
+
+/// Validate one submission. `last_fence` is the highest non-zero `signal_fence` accepted since the
+/// last ring reset.
+pub(crate) fn validate_submission(
+    mem: &mut dyn MemoryBus,
+    desc: &ring::AerogpuSubmitDesc,
+    last_fence: u64,
+) -> Result<(), Violation> {
+    if let Err(err) = desc.validate_prefix() {
+        return Err(violation(
+            AerogpuErrorCode::CmdDecode,
+            format!("submit descriptor: {err}"),
+        ));
+    }
+
+    let alloc_refs = validate_cmd_stream(mem, desc)?;
+    validate_alloc_table(mem, desc, &alloc_refs)?;
+
+    let signal_fence = desc.signal_fence;
+    if signal_fence != 0 && signal_fence < last_fence {
+        return Err(violation(
+            AerogpuErrorCode::FenceOrder,
+            format!("signal_fence {signal_fence} is lower than earlier fence {last_fence}"),
+        ));
+    }
+    Ok(())
+}
+
+/// A `backing_alloc_id` reference found in the command stream.
+struct AllocRef {
+    packet_offset: u32,
+    alloc_id: u32,
+}
+
+fn validate_cmd_stream(
+    mem: &mut dyn MemoryBus,
+    desc: &ring::AerogpuSubmitDesc,
+) -> Result<Vec<AllocRef>, Violation> {
+    let cmd_gpa = desc.cmd_gpa;
+    let cmd_size_bytes = desc.cmd_size_bytes;
+    let mut alloc_refs = Vec::new();
+
+    if cmd_gpa == 0 && cmd_size_bytes == 0 {
+        return Ok(alloc_refs);
+    }
+    if cmd_gpa == 0 || cmd_size_bytes == 0 {
+        return Err(violation(
+            AerogpuErrorCode::CmdStreamHeader,
+            format!("cmd_gpa=0x{cmd_gpa:x} with cmd_size_bytes={cmd_size_bytes}"),
+        ));
+    }
+    if cmd_gpa.checked_add(u64::from(cmd_size_bytes)).is_none() {
+        return Err(violation(
+            AerogpuErrorCode::Oob,
+            format!("command buffer 0x{cmd_gpa:x}+{cmd_size_bytes} wraps the address space"),
+        ));
+    }
+
+    let header_size = cmd::AerogpuCmdStreamHeader::SIZE_BYTES as u32;
+    if cmd_size_bytes < header_size {
+        return Err(violation(
+            AerogpuErrorCode::CmdStreamHeader,
+            format!("cmd_size_bytes {cmd_size_bytes} is smaller than the stream header"),
+        ));
+    }
+    let mut header_buf = [0u8; cmd::AerogpuCmdStreamHeader::SIZE_BYTES];
+    mem.read_physical(cmd_gpa, &mut header_buf);
+    let stream_size = match cmd::decode_cmd_stream_header_le(&header_buf) {
+        Ok(header) => header.size_bytes,
+        Err(err) => {
+            return Err(violation(
+                AerogpuErrorCode::CmdStreamHeader,
+                format!("stream header: {err}"),
+            ))
+        }
+    };
+    if stream_size > cmd_size_bytes {
+        return Err(violation(
+            AerogpuErrorCode::CmdStreamHeader,
+            format!("stream size_bytes {stream_size} exceeds cmd_size_bytes {cmd_size_bytes}"),
+        ));
+    }
+    if stream_size > MAX_CMD_STREAM_SIZE_BYTES {
+        return Err(violation(
+            AerogpuErrorCode::CmdStreamHeader,
+            format!("stream size_bytes {stream_size} exceeds the device limit"),
+        ));
+    }
+
+    let mut offset = header_size;
+    while offset < stream_size {
+        if stream_size - offset < cmd::AerogpuCmdHdr::SIZE_BYTES as u32 {
+            return Err(violation(
+                AerogpuErrorCode::PacketSize,
+                format!("truncated packet header at offset {offset}"),
+            ));
+        }
+        // In bounds: the whole command buffer was checked against address-space wrap above.
+        let packet_gpa = cmd_gpa + u64::from(offset);
+        let mut hdr_buf = [0u8; cmd::AerogpuCmdHdr::SIZE_BYTES];
+        mem.read_physical(packet_gpa, &mut hdr_buf);
+        let opcode = u32::from_le_bytes(hdr_buf[0..4].try_into().unwrap());
+        let size_bytes = u32::from_le_bytes(hdr_buf[4..8].try_into().unwrap());
+
+        if !size_bytes.is_multiple_of(4) {
+            return Err(violation(
+                AerogpuErrorCode::PacketAlignment,
+                format!(
+                    "packet 0x{opcode:x} at offset {offset}: size_bytes {size_bytes} is not 4-byte aligned"
+                ),
+            ));
+        }
+        let min_size = min_packet_size(opcode);
+        if size_bytes < min_size {
+            return Err(violation(
+                AerogpuErrorCode::PacketSize,
+                format!(
+                    "packet 0x{opcode:x} at offset {offset}: size_bytes {size_bytes} is below the minimum {min_size}"
+                ),
+            ));
+        }
+        if size_bytes > stream_size - offset {
+            return Err(violation(
+                AerogpuErrorCode::PacketSize,
+                format!(
+                    "packet 0x{opcode:x} at offset {offset}: size_bytes {size_bytes} overruns the {stream_size}-byte stream"
+                ),
+            ));
+        }
+
+        if let Some(field_offset) = backing_alloc_id_offset(opcode) {
+            let mut id_buf = [0u8; 4];
+            mem.read_physical(packet_gpa + field_offset, &mut id_buf);
+            let alloc_id = u32::from_le_bytes(id_buf);
+            if alloc_id != 0 {
+                alloc_refs.push(AllocRef {
+                    packet_offset: offset,
+                    alloc_id,
+                });
+            }
+        }
+
+        offset += size_bytes;
+    }
+
+    Ok(alloc_refs)
+}
+
+fn validate_alloc_table(
+    mem: &mut dyn MemoryBus,
+    desc: &ring::AerogpuSubmitDesc,
+    alloc_refs: &[AllocRef],
+) -> Result<(), Violation> {
+    let table_gpa = desc.alloc_table_gpa;
+    let table_size_bytes = desc.alloc_table_size_bytes;
+
+    if table_gpa == 0 && table_size_bytes == 0 {
+        return match alloc_refs.first() {
+            Some(alloc_ref) => Err(violation(
+                AerogpuErrorCode::AllocTable,
+                format!(
+                    "packet at offset {} references alloc_id {} but the submission has no alloc table",
+                    alloc_ref.packet_offset, alloc_ref.alloc_id
+                ),
+            )),
+            None => Ok(()),
+        };
+    }
+    if table_gpa == 0 || table_size_bytes == 0 {
+        return Err(violation(
+            AerogpuErrorCode::AllocTable,
+            format!(
+                "alloc_table_gpa=0x{table_gpa:x} with alloc_table_size_bytes={table_size_bytes}"
+            ),
+        ));
+    }
+    if table_gpa.checked_add(u64::from(table_size_bytes)).is_none() {
+        return Err(violation(
+            AerogpuErrorCode::Oob,
+            format!("alloc table 0x{table_gpa:x}+{table_size_bytes} wraps the address space"),
+        ));
+    }
+    if table_size_bytes > MAX_AEROGPU_ALLOC_TABLE_BYTES {
+        return Err(violation(
+            AerogpuErrorCode::AllocTable,
+            format!("alloc_table_size_bytes {table_size_bytes} exceeds the device limit"),
+        ));
+    }
+
+    let mut bytes = Vec::new();
+    if bytes.try_reserve_exact(table_size_bytes as usize).is_err() {
+        return Err(violation(
+            AerogpuErrorCode::Internal,
+            "out of memory reading the alloc table",
+        ));
+    }
+    bytes.resize(table_size_bytes as usize, 0u8);
+    mem.read_physical(table_gpa, &mut bytes);
+    let table = match ring::decode_alloc_table_le(&bytes) {
+        Ok(table) => table,
+        Err(err) => {
+            return Err(violation(
+                AerogpuErrorCode::AllocTable,
+                format!("alloc table: {err}"),
+            ))
+        }
+    };
+
+    for alloc_ref in alloc_refs {
+        if ring::lookup_alloc(&table, alloc_ref.alloc_id).is_none() {
+            return Err(violation(
+                AerogpuErrorCode::AllocTable,
+                format!(
+                    "packet at offset {} references alloc_id {} missing from the alloc table",
+                    alloc_ref.packet_offset, alloc_ref.alloc_id
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Smallest valid `size_bytes` for a packet: the fixed layout of known opcodes, the bare packet
+/// header otherwise. Packets may be larger (trailing payloads, forward-compatible extensions).
+fn min_packet_size(opcode: u32) -> u32 {
+    use cmd::AerogpuCmdOpcode as Op;
+
+    let size = match Op::from_u32(opcode) {
+        Some(Op::CreateBuffer) => cmd::AerogpuCmdCreateBuffer::SIZE_BYTES,
+        Some(Op::CreateTexture2d) => cmd::AerogpuCmdCreateTexture2d::SIZE_BYTES,
+        Some(Op::DestroyResource) => cmd::AerogpuCmdDestroyResource::SIZE_BYTES,
+        Some(Op::ResourceDirtyRange) => cmd::AerogpuCmdResourceDirtyRange::SIZE_BYTES,
+        Some(Op::UploadResource) => cmd::AerogpuCmdUploadResource::SIZE_BYTES,
+        Some(Op::CopyBuffer) => cmd::AerogpuCmdCopyBuffer::SIZE_BYTES,
+        Some(Op::CopyTexture2d) => cmd::AerogpuCmdCopyTexture2d::SIZE_BYTES,
+        Some(Op::CreateTextureView) => cmd::AerogpuCmdCreateTextureView::SIZE_BYTES,
+        Some(Op::DestroyTextureView) => cmd::AerogpuCmdDestroyTextureView::SIZE_BYTES,
+        Some(Op::CreateShaderDxbc) => cmd::AerogpuCmdCreateShaderDxbc::SIZE_BYTES,
+        Some(Op::DestroyShader) => cmd::AerogpuCmdDestroyShader::SIZE_BYTES,
+        Some(Op::BindShaders) => cmd::AerogpuCmdBindShaders::SIZE_BYTES,
+        Some(Op::SetShaderConstantsF) => cmd::AerogpuCmdSetShaderConstantsF::SIZE_BYTES,
+        Some(Op::SetShaderConstantsI) => cmd::AerogpuCmdSetShaderConstantsI::SIZE_BYTES,
+        Some(Op::SetShaderConstantsB) => cmd::AerogpuCmdSetShaderConstantsB::SIZE_BYTES,
+        Some(Op::CreateInputLayout) => cmd::AerogpuCmdCreateInputLayout::SIZE_BYTES,
+        Some(Op::DestroyInputLayout) => cmd::AerogpuCmdDestroyInputLayout::SIZE_BYTES,
+        Some(Op::SetInputLayout) => cmd::AerogpuCmdSetInputLayout::SIZE_BYTES,
+        Some(Op::SetBlendState) => cmd::AerogpuCmdSetBlendState::SIZE_BYTES,
+        Some(Op::SetDepthStencilState) => cmd::AerogpuCmdSetDepthStencilState::SIZE_BYTES,
+        Some(Op::SetRasterizerState) => cmd::AerogpuCmdSetRasterizerState::SIZE_BYTES,
+        Some(Op::SetRenderTargets) => cmd::AerogpuCmdSetRenderTargets::SIZE_BYTES,
+        Some(Op::SetViewport) => cmd::AerogpuCmdSetViewport::SIZE_BYTES,
+        Some(Op::SetScissor) => cmd::AerogpuCmdSetScissor::SIZE_BYTES,
+        Some(Op::SetVertexBuffers) => cmd::AerogpuCmdSetVertexBuffers::SIZE_BYTES,
+        Some(Op::SetIndexBuffer) => cmd::AerogpuCmdSetIndexBuffer::SIZE_BYTES,
+        Some(Op::SetPrimitiveTopology) => cmd::AerogpuCmdSetPrimitiveTopology::SIZE_BYTES,
+        Some(Op::SetTexture) => cmd::AerogpuCmdSetTexture::SIZE_BYTES,
+        Some(Op::SetSamplerState) => cmd::AerogpuCmdSetSamplerState::SIZE_BYTES,
+        Some(Op::SetRenderState) => cmd::AerogpuCmdSetRenderState::SIZE_BYTES,
+        Some(Op::CreateSampler) => cmd::AerogpuCmdCreateSampler::SIZE_BYTES,
+        Some(Op::DestroySampler) => cmd::AerogpuCmdDestroySampler::SIZE_BYTES,
+        Some(Op::SetSamplers) => cmd::AerogpuCmdSetSamplers::SIZE_BYTES,
+        Some(Op::SetConstantBuffers) => cmd::AerogpuCmdSetConstantBuffers::SIZE_BYTES,
+        Some(Op::SetShaderResourceBuffers) => cmd::AerogpuCmdSetShaderResourceBuffers::SIZE_BYTES,
+        Some(Op::SetUnorderedAccessBuffers) => cmd::AerogpuCmdSetUnorderedAccessBuffers::SIZE_BYTES,
+        Some(Op::Clear) => cmd::AerogpuCmdClear::SIZE_BYTES,
+        Some(Op::Draw) => cmd::AerogpuCmdDraw::SIZE_BYTES,
+        Some(Op::DrawIndexed) => cmd::AerogpuCmdDrawIndexed::SIZE_BYTES,
+        Some(Op::Dispatch) => cmd::AerogpuCmdDispatch::SIZE_BYTES,
+        Some(Op::Present) => cmd::AerogpuCmdPresent::SIZE_BYTES,
+        Some(Op::PresentEx) => cmd::AerogpuCmdPresentEx::SIZE_BYTES,
+        Some(Op::ExportSharedSurface) => cmd::AerogpuCmdExportSharedSurface::SIZE_BYTES,
+        Some(Op::ImportSharedSurface) => cmd::AerogpuCmdImportSharedSurface::SIZE_BYTES,
+        Some(Op::ReleaseSharedSurface) => cmd::AerogpuCmdReleaseSharedSurface::SIZE_BYTES,
+        Some(Op::Flush) => cmd::AerogpuCmdFlush::SIZE_BYTES,
+        Some(Op::Nop) | Some(Op::DebugMarker) | None => cmd::AerogpuCmdHdr::SIZE_BYTES,
+    };
+    size as u32
+}
+
+/// Offset of `backing_alloc_id` within packets that bind guest memory to a resource.
+fn backing_alloc_id_offset(opcode: u32) -> Option<u64> {
+    use cmd::AerogpuCmdOpcode as Op;
+
+    let offset = match Op::from_u32(opcode)? {
+        Op::CreateBuffer => core::mem::offset_of!(cmd::AerogpuCmdCreateBuffer, backing_alloc_id),
+        Op::CreateTexture2d => {
+            core::mem::offset_of!(cmd::AerogpuCmdCreateTexture2d, backing_alloc_id)
+        }
+        _ => return None,
+    };
+    Some(offset as u64)
+}
+
+fn capture_prefix(mem: &mut dyn MemoryBus, gpa: u64, size_bytes: u32) -> Vec<u8> {
+    let len = (size_bytes as usize).min(AEROGPU_VALIDATION_CAPTURE_BYTES);
+    if gpa == 0 || len == 0 || gpa.checked_add(len as u64).is_none() {
+        return Vec::new();
+    }
+    let mut out = vec![0u8; len];
+    mem.read_physical(gpa, &mut out);
+    out
+}
@@ -0,0 +1,217 @@
+//! Synthetic "aero-bench" port-I/O device for guest-to-host microbenchmarks (see
+//! [`crate::MachineConfig::enable_aero_bench`]).
+//!
+//! The device decodes [`AERO_BENCH_PORT_COUNT`] ports starting at [`AERO_BENCH_PORT_BASE`] as
+//! four dword registers:
+//!
+//! | offset | write                                   | read        |
+//! |--------|-----------------------------------------|-------------|
+//! | `+0x0` | `CMD`: `op + (arg << 8)` (runs the op)  | `RESULT_LO` |
+//! | `+0x4` | `ADDR_LO` (guest-physical buffer)       | `ADDR_LO`   |
+//! | `+0x8` | `ADDR_HI`                               | `ADDR_HI`   |
+//! | `+0xC` | ignored                                 | `RESULT_HI` |
+//!
+//! Every executed op records the TSC delta since the previous op, so a guest loop issuing the
+//! same op back to back measures the full round trip (instruction dispatch, port decode and
+//! device work). The device is not described in ACPI or SMBIOS; guests only find it by probing
+//! for [`AERO_BENCH_SIGNATURE`] with [`BenchOp::Pattern`].
+
+/// First I/O port decoded by the aero-bench device.
+///
+/// Chosen below the PCI I/O window and clear of the ISA/ACPI fixed ports the machine exposes.
+pub const AERO_BENCH_PORT_BASE: u16 = 0x0A80;
+/// Number of I/O ports decoded by the aero-bench device.
+pub const AERO_BENCH_PORT_COUNT: u16 = 0x10;
+/// `RESULT_LO` value after [`BenchOp::Pattern`] (`"AERB"` little-endian).
+pub const AERO_BENCH_SIGNATURE: u32 = u32::from_le_bytes(*b"AERB");
+/// Size of the host scratch buffer [`BenchOp::Memcpy`] copies into; larger requests are clamped.
+pub const AERO_BENCH_SCRATCH_BYTES: usize = 64 * 1024;
+
+const REG_CMD: u16 = 0x0;
+const REG_ADDR_LO: u16 = 0x4;
+const REG_ADDR_HI: u16 = 0x8;
+const REG_RESULT_HI: u16 = 0xC;
+
+/// Operation selected by the low byte of a `CMD` write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BenchOp {
+    /// Do nothing; measures the bare port-I/O round trip.
+    Nop = 0,
+    /// Result = ([`AERO_BENCH_SIGNATURE`], `arg`).
+    Pattern = 1,
+    /// Copy `arg` bytes from guest-physical `ADDR` into the host scratch buffer. Result = bytes
+    /// copied.
+    Memcpy = 2,
+    /// Result = current guest time in nanoseconds (`RESULT_HI:RESULT_LO`).
+    Timestamp = 3,
+}
+
+impl BenchOp {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Nop),
+            1 => Some(Self::Pattern),
+            2 => Some(Self::Memcpy),
+            3 => Some(Self::Timestamp),
+            _ => None,
+        }
+    }
+}
+
+/// Per-op counters recorded by the aero-bench device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BenchOpStats {
+    /// Number of times the op was issued.
+    pub count: u64,
+    /// Sum of guest TSC cycles elapsed between the previous op and each issue of this op.
+    pub cycles: u64,
+    /// Guest bytes transferred (only [`BenchOp::Memcpy`]).
+    pub bytes: u64,
+}
+
+impl BenchOpStats {
+    /// Average cycles per op, or `0` if the op was never issued.
+    pub fn cycles_per_op(&self) -> u64 {
+        self.cycles.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+/// Snapshot of the aero-bench counters (see [`crate::Machine::bench_results`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BenchResults {
+    pub nop: BenchOpStats,
+    pub pattern: BenchOpStats,
+    pub memcpy: BenchOpStats,
+    pub timestamp: BenchOpStats,
+    /// `CMD` writes with an unknown op byte (ignored).
+    pub unknown_ops: u64,
+}
+
+impl BenchResults {
+    /// Counters for `op`.
+    pub fn op(&self, op: BenchOp) -> &BenchOpStats {
+        match op {
+            BenchOp::Nop => &self.nop,
+            BenchOp::Pattern => &self.pattern,
+            BenchOp::Memcpy => &self.memcpy,
+            BenchOp::Timestamp => &self.timestamp,
+        }
+    }
+
+    fn op_mut(&mut self, op: BenchOp) -> &mut BenchOpStats {
+        match op {
+            BenchOp::Nop => &mut self.nop,
+            BenchOp::Pattern => &mut self.pattern,
+            BenchOp::Memcpy => &mut self.memcpy,
+            BenchOp::Timestamp => &mut self.timestamp,
+        }
+    }
+}
+
+pub(crate) struct BenchDevice {
+    addr: u64,
+    result: u64,
+    // Guest TSC at the previous op; `None` until the first op after reset.
+    last_tsc: Option<u64>,
+    scratch: Box<[u8]>,
+    results: BenchResults,
+}
+
+impl BenchDevice {
+    pub(crate) fn new() -> Self {
+        Self {
+            addr: 0,
+            result: 0,
+            last_tsc: None,
+            scratch: vec![0u8; AERO_BENCH_SCRATCH_BYTES].into_boxed_slice(),
+            results: BenchResults::default(),
+        }
+    }
+
+    pub(crate) fn decodes(port: u16) -> bool {
+        port.wrapping_sub(AERO_BENCH_PORT_BASE) < AERO_BENCH_PORT_COUNT
+    }
+
+    pub(crate) fn results(&self) -> BenchResults {
+        self.results
+    }
+
+    /// Clear the counters; the next op starts a fresh TSC delta.
+    pub(crate) fn clear_results(&mut self) {
+        self.results = BenchResults::default();
+        self.last_tsc = None;
+    }
+
+    pub(crate) fn read(&self, port: u16, size: u8) -> u32 {
+        let offset = port.wrapping_sub(AERO_BENCH_PORT_BASE);
+        let reg = match offset & !3 {
+            REG_CMD => self.result as u32,
+            REG_ADDR_LO => self.addr as u32,
+            REG_ADDR_HI => (self.addr >> 32) as u32,
+            REG_RESULT_HI => (self.result >> 32) as u32,
+            _ => return u32::MAX,
+        };
+        let value = reg >> ((offset & 3) * 8);
+        match size {
+            1 => value & 0xFF,
+            2 => value & 0xFFFF,
+            _ => value,
+        }
+    }
+
+    /// Handle a register write. `tsc` is the issuing vCPU's TSC and `now_ns` the current guest
+    /// time; `mem` is the guest-physical view used by [`BenchOp::Memcpy`].
+    pub(crate) fn write(
+        &mut self,
+        port: u16,
+        size: u8,
+        value: u32,
+        tsc: u64,
+        now_ns: u64,
+        mem: &mut impl memory::MemoryBus,
+    ) {
+        let offset = port.wrapping_sub(AERO_BENCH_PORT_BASE);
+        match offset {
+            REG_CMD => self.command(value, tsc, now_ns, mem),
+            REG_ADDR_LO..=0x7 | REG_ADDR_HI..=0xB => {
+                let shift = u32::from(offset - REG_ADDR_LO) * 8;
+                let mask: u64 = match size {
+                    1 => 0xFF,
+                    2 => 0xFFFF,
+                    _ => 0xFFFF_FFFF,
+                };
+                let mask = mask << shift;
+                self.addr = (self.addr & !mask) | ((u64::from(value) << shift) & mask);
+            }
+            _ => {}
+        }
+    }
+
+    fn command(&mut self, value: u32, tsc: u64, now_ns: u64, mem: &mut impl memory::MemoryBus) {
+        let Some(op) = BenchOp::from_u8(value as u8) else {
+            self.results.unknown_ops = self.results.unknown_ops.wrapping_add(1);
+            return;
+        };
+        let arg = value >> 8;
+        let mut bytes = 0u64;
+        self.result = match op {
+            BenchOp::Nop => self.result,
+            BenchOp::Pattern => u64::from(AERO_BENCH_SIGNATURE) | (u64::from(arg) << 32),
+            BenchOp::Memcpy => {
+                let len = (arg as usize).min(self.scratch.len());
+                mem.read_physical(self.addr, &mut self.scratch[..len]);
+                bytes = len as u64;
+                bytes
+            }
+            BenchOp::Timestamp => now_ns,
+        };
+
+        let cycles = self.last_tsc.map_or(0, |last| tsc.saturating_sub(last));
+        self.last_tsc = Some(tsc);
+        let stats = self.results.op_mut(op);
+        stats.count = stats.count.wrapping_add(1);
+        stats.cycles = stats.cycles.wrapping_add(cycles);
+        stats.bytes = stats.bytes.wrapping_add(bytes);
+    }
+}
@@ -0,0 +1,91 @@
+//! Coarse boot-progress detection (see [`crate::Machine::boot_stage`]).
+//!
+//! The detector never parses guest output. It advances on architectural events that the machine
+//! already observes: firmware POST completion, the guest's first INT 13h disk read, the BSP
+//! leaving real mode, a ring-0 PCI configuration write after that switch, the AeroGPU WDDM
+//! scanout claim and the first NIC frame. Stages only move forward; an event for a stage at or
+//! below the current one is ignored, so a guest that brings up networking before its display
+//! driver skips [`BootStage::WddmScanout`] rather than moving backwards.
+
+/// Boot progress, in the order a typical Windows 7 boot reaches each stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum BootStage {
+    /// Reset has happened but firmware POST has not completed (or POST was skipped).
+    #[default]
+    Firmware,
+    /// Firmware POST completed and handed control to the boot sector.
+    PostComplete,
+    /// The guest issued its first INT 13h disk read (boot loader running).
+    Bootloader,
+    /// The BSP switched to protected or long mode.
+    KernelStarting,
+    /// Ring-0 code wrote PCI configuration space after the mode switch (OS device enumeration).
+    DeviceEnumeration,
+    /// The AeroGPU WDDM driver claimed scanout (desktop/login screen imminent).
+    WddmScanout,
+    /// The first Ethernet frame crossed the NIC in either direction.
+    NetworkActive,
+}
+
+/// A recorded [`BootStage`] transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootStageTransition {
+    pub stage: BootStage,
+    /// Guest time of the transition in nanoseconds since reset (platform clock when the PC
+    /// platform is enabled, otherwise derived from the BSP's TSC).
+    pub guest_time_ns: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BootStageTracker {
+    stage: BootStage,
+    // Bumped on every transition and on reset so hosts can poll a single integer.
+    changes: u64,
+    transitions: Vec<BootStageTransition>,
+    // PCI config write count sampled when the guest left real mode.
+    pci_config_writes_at_mode_switch: u64,
+}
+
+impl BootStageTracker {
+    pub(crate) fn stage(&self) -> BootStage {
+        self.stage
+    }
+
+    pub(crate) fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    pub(crate) fn transitions(&self) -> &[BootStageTransition] {
+        &self.transitions
+    }
+
+    pub(crate) fn pci_config_writes_at_mode_switch(&self) -> u64 {
+        self.pci_config_writes_at_mode_switch
+    }
+
+    pub(crate) fn set_pci_config_writes_at_mode_switch(&mut self, writes: u64) {
+        self.pci_config_writes_at_mode_switch = writes;
+    }
+
+    /// Return to [`BootStage::Firmware`] and drop recorded transitions (a new boot starts).
+    pub(crate) fn reset(&mut self) {
+        self.stage = BootStage::Firmware;
+        self.transitions.clear();
+        self.pci_config_writes_at_mode_switch = 0;
+        self.changes = self.changes.wrapping_add(1);
+    }
+
+    /// Advance to `stage` if it is past the current stage. Returns whether a transition happened.
+    pub(crate) fn advance(&mut self, stage: BootStage, guest_time_ns: u64) -> bool {
+        if stage <= self.stage {
+            return false;
+        }
+        self.stage = stage;
+        self.transitions.push(BootStageTransition {
+            stage,
+            guest_time_ns,
+        });
+        self.changes = self.changes.wrapping_add(1);
+        true
+    }
+}
@@ -0,0 +1,260 @@
+//! Opt-in diagnostics for guest code that writes where it should not (see
+//! [`crate::Machine::set_rom_write_monitor`] and [`crate::Machine::set_smc_detection`]).
+//!
+//! The ROM-write monitor records writes that hit a mapped ROM range, which the physical bus
+//! otherwise drops silently. The self-modifying-code (SMC) detector works at batch granularity:
+//! after each vCPU batch it intersects the physical pages instructions were fetched from with the
+//! RAM pages the batch wrote (reported by the dirty tracker's write log) and records every page in
+//! both sets together with the RIPs that executed from it.
+//!
+//! Both are host state: they are not snapshotted and survive [`crate::Machine::reset`]. While
+//! both are disabled the CPU bus does not consult the tracker at all.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use memory::DirtyTracker;
+
+use crate::guest_ram;
+
+/// Maximum number of distinct entries retained in the ROM-write ring.
+pub const ROM_WRITE_LOG_CAP: usize = 256;
+
+/// Maximum number of distinct pages retained in the SMC ring.
+pub const SMC_LOG_CAP: usize = 256;
+
+/// Maximum number of RIPs kept per [`SmcOccurrence`].
+pub const SMC_MAX_RIPS: usize = 8;
+
+const PAGE_SHIFT: u32 = 12;
+
+/// One deduplicated write to a mapped ROM range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomWrite {
+    /// Guest physical address of the first byte that hit the ROM.
+    pub paddr: u64,
+    /// Number of bytes that hit the ROM.
+    pub size: u32,
+    /// Value of the most recent occurrence (its first eight bytes, little-endian).
+    pub value: u64,
+    /// RIP of the most recent occurrence; `None` for writes made outside a vCPU batch (firmware
+    /// HLE, device DMA).
+    pub rip: Option<u64>,
+    pub count: u64,
+}
+
+/// Contents of the ROM-write ring (see [`crate::Machine::take_rom_write_log`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomWriteLog {
+    /// Least recently seen first.
+    pub entries: Vec<RomWrite>,
+    /// Distinct writes evicted because the ring held [`ROM_WRITE_LOG_CAP`] entries.
+    pub dropped: u64,
+}
+
+/// A physical page that one batch both executed from and wrote to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmcOccurrence {
+    /// Guest physical address of the 4 KiB page.
+    pub page: u64,
+    /// Distinct RIPs of the instructions fetched from the page by the most recent such batch, in
+    /// fetch order (at most [`SMC_MAX_RIPS`]).
+    pub rips: Vec<u64>,
+    /// Number of batches that executed from and wrote to the page.
+    pub count: u64,
+}
+
+/// Contents of the SMC ring (see [`crate::Machine::take_smc_log`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SmcLog {
+    /// Least recently seen first.
+    pub entries: Vec<SmcOccurrence>,
+    /// Distinct pages evicted because the ring held [`SMC_LOG_CAP`] entries.
+    pub dropped: u64,
+}
+
+/// Shared between the machine, its CPU bus and the ROM-write bus hook.
+#[derive(Debug, Default)]
+pub(crate) struct CodeDiagnostics {
+    rom_writes: Cell<bool>,
+    smc: Cell<bool>,
+    // RIP of the vCPU currently executing a batch; `None` outside of CPU execution.
+    cpu_rip: Cell<Option<u64>>,
+    rom_ring: RefCell<VecDeque<RomWrite>>,
+    rom_dropped: Cell<u64>,
+    // `(physical page number, RIP)` of each fetch in the current batch.
+    executed: RefCell<Vec<(u64, u64)>>,
+    smc_ring: RefCell<VecDeque<SmcOccurrence>>,
+    smc_dropped: Cell<u64>,
+}
+
+impl CodeDiagnostics {
+    pub(crate) fn set_rom_writes_enabled(&self, enabled: bool) {
+        self.rom_writes.set(enabled);
+    }
+
+    pub(crate) fn smc_enabled(&self) -> bool {
+        self.smc.get()
+    }
+
+    pub(crate) fn set_smc_enabled(&self, enabled: bool) {
+        self.smc.set(enabled);
+        self.executed.borrow_mut().clear();
+    }
+
+    /// Whether the CPU bus needs to report to the tracker at all.
+    pub(crate) fn is_active(&self) -> bool {
+        self.rom_writes.get() || self.smc.get()
+    }
+
+    /// Called at each instruction boundary of a CPU batch.
+    pub(crate) fn enter_cpu(&self, rip: u64) {
+        self.cpu_rip.set(Some(rip));
+    }
+
+    /// Called before a CPU batch: drops writes made since the previous batch (firmware HLE,
+    /// device DMA) from the dirty tracker's write log.
+    pub(crate) fn begin_batch(&self, rip: u64, dirty: &DirtyTracker) {
+        self.enter_cpu(rip);
+        if self.smc.get() {
+            dirty.take_write_log();
+        }
+    }
+
+    /// Called after a CPU batch: records every executed page the batch also wrote.
+    pub(crate) fn finish_batch(&self, dirty: &DirtyTracker, ram_size_bytes: u64) {
+        self.cpu_rip.set(None);
+        if !self.smc.get() {
+            return;
+        }
+        let executed = std::mem::take(&mut *self.executed.borrow_mut());
+        let written = dirty.take_write_log().unwrap_or_default();
+        if executed.is_empty() || written.is_empty() {
+            return;
+        }
+
+        // The write log is in RAM-offset space; executed pages are guest-physical.
+        let layout = guest_ram::layout(ram_size_bytes);
+        let page_size = u64::from(dirty.page_size());
+        let mut written: Vec<u64> = written
+            .into_iter()
+            .filter_map(|page| {
+                let (gpa, _) = guest_ram::backing_offset_to_gpa(&layout, page * page_size)?;
+                Some(gpa >> PAGE_SHIFT)
+            })
+            .collect();
+        written.sort_unstable();
+        written.dedup();
+
+        let mut hits: Vec<(u64, Vec<u64>)> = Vec::new();
+        for (page, rip) in executed {
+            if written.binary_search(&page).is_err() {
+                continue;
+            }
+            let rips = match hits.iter().position(|(hit, _)| *hit == page) {
+                Some(idx) => &mut hits[idx].1,
+                None => {
+                    hits.push((page, Vec::new()));
+                    &mut hits.last_mut().expect("just pushed").1
+                }
+            };
+            if rips.len() < SMC_MAX_RIPS && !rips.contains(&rip) {
+                rips.push(rip);
+            }
+        }
+        for (page, rips) in hits {
+            self.record_smc(page << PAGE_SHIFT, rips);
+        }
+    }
+
+    /// Called for each instruction fetch while SMC detection is enabled.
+    pub(crate) fn record_fetch(&self, paddr: u64) {
+        let Some(rip) = self.cpu_rip.get() else {
+            return;
+        };
+        let entry = (paddr >> PAGE_SHIFT, rip);
+        let mut executed = self.executed.borrow_mut();
+        if executed.last() != Some(&entry) {
+            executed.push(entry);
+        }
+    }
+
+    pub(crate) fn record_rom_write(&self, paddr: u64, bytes: &[u8]) {
+        if !self.rom_writes.get() || bytes.is_empty() {
+            return;
+        }
+        let size = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+        let mut value = [0u8; 8];
+        let value_len = bytes.len().min(value.len());
+        value[..value_len].copy_from_slice(&bytes[..value_len]);
+        let value = u64::from_le_bytes(value);
+        let rip = self.cpu_rip.get();
+
+        let mut ring = self.rom_ring.borrow_mut();
+        let existing = ring
+            .iter()
+            .position(|entry| entry.paddr == paddr && entry.size == size);
+        let entry = match existing.and_then(|idx| ring.remove(idx)) {
+            Some(entry) => RomWrite {
+                value,
+                rip,
+                count: entry.count.saturating_add(1),
+                ..entry
+            },
+            None => {
+                if ring.len() == ROM_WRITE_LOG_CAP {
+                    ring.pop_front();
+                    self.rom_dropped
+                        .set(self.rom_dropped.get().saturating_add(1));
+                }
+                RomWrite {
+                    paddr,
+                    size,
+                    value,
+                    rip,
+                    count: 1,
+                }
+            }
+        };
+        ring.push_back(entry);
+    }
+
+    fn record_smc(&self, page: u64, rips: Vec<u64>) {
+        let mut ring = self.smc_ring.borrow_mut();
+        let existing = ring.iter().position(|entry| entry.page == page);
+        let entry = match existing.and_then(|idx| ring.remove(idx)) {
+            Some(entry) => SmcOccurrence {
+                page,
+                rips,
+                count: entry.count.saturating_add(1),
+            },
+            None => {
+                if ring.len() == SMC_LOG_CAP {
+                    ring.pop_front();
+                    self.smc_dropped
+                        .set(self.smc_dropped.get().saturating_add(1));
+                }
+                SmcOccurrence {
+                    page,
+                    rips,
+                    count: 1,
+                }
+            }
+        };
+        ring.push_back(entry);
+    }
+
+    pub(crate) fn take_rom_write_log(&self) -> RomWriteLog {
+        RomWriteLog {
+            entries: self.rom_ring.borrow_mut().drain(..).collect(),
+            dropped: self.rom_dropped.replace(0),
+        }
+    }
+
+    pub(crate) fn take_smc_log(&self) -> SmcLog {
+        SmcLog {
+            entries: self.smc_ring.borrow_mut().drain(..).collect(),
+            dropped: self.smc_dropped.replace(0),
+        }
+    }
+}
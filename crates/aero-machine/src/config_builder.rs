@@ -0,0 +1,364 @@
+//! Typed construction of [`MachineConfig`] (see [`MachineConfig::builder`]).
+//!
+//! [`MachineConfig`] is a flat struct of mostly independent `enable_*` flags, and the constraints
+//! between them (AeroGPU vs VGA, a single NIC, PCI devices requiring the PC platform) are only
+//! checked by [`Machine::new`]. The builder groups related flags into small enums/structs so the
+//! mutually exclusive choices cannot be expressed at all, and runs the same validation as
+//! [`Machine::new`] in [`MachineConfigBuilder::build`] so the remaining mistakes surface where the
+//! configuration is assembled.
+//!
+//! [`MachineConfig::diff`] lists the fields that differ between two configurations; presets and
+//! tests use it to pin down exactly what a preset changes relative to [`MachineConfig::default`].
+
+use std::fmt;
+
+use crate::{BootDevice, Machine, MachineConfig, MachineError, NumaNodeConfig};
+
+/// Guest-visible network adapter (at most one NIC can be attached).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NicKind {
+    /// No NIC.
+    #[default]
+    None,
+    /// Intel E1000 (`MachineConfig::enable_e1000`).
+    E1000 { mac_addr: Option<[u8; 6]> },
+    /// virtio-net (`MachineConfig::enable_virtio_net`).
+    VirtioNet { mac_addr: Option<[u8; 6]> },
+}
+
+/// Display adapter (AeroGPU and the standalone VGA/VBE device are mutually exclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuKind {
+    /// Headless: neither VGA nor AeroGPU.
+    None,
+    /// Transitional standalone VGA/VBE device (`MachineConfig::enable_vga`).
+    Vga,
+    /// AeroGPU PCI device (`MachineConfig::enable_aerogpu`); requires the PC platform.
+    AeroGpu,
+}
+
+/// Storage controller set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StorageTopology {
+    pub ahci: bool,
+    pub ide: bool,
+    pub nvme: bool,
+    pub virtio_blk: bool,
+}
+
+impl StorageTopology {
+    /// No PCI storage controllers.
+    pub const NONE: Self = Self {
+        ahci: false,
+        ide: false,
+        nvme: false,
+        virtio_blk: false,
+    };
+
+    /// Canonical Windows 7 topology: AHCI (ICH9) + IDE (PIIX3), see
+    /// `docs/05-storage-topology-win7.md`.
+    pub const WIN7: Self = Self {
+        ahci: true,
+        ide: true,
+        nvme: false,
+        virtio_blk: false,
+    };
+}
+
+/// USB host controller set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UsbControllers {
+    pub uhci: bool,
+    pub ehci: bool,
+    pub xhci: bool,
+    /// Built-in USB HID keyboard/mouse/gamepad/consumer-control behind the UHCI root hub.
+    pub synthetic_usb_hid: bool,
+    /// Put the synthetic HID devices behind xHCI root port 0 instead of UHCI.
+    pub synthetic_usb_hid_on_xhci: bool,
+}
+
+impl UsbControllers {
+    /// No USB controllers.
+    pub const NONE: Self = Self {
+        uhci: false,
+        ehci: false,
+        xhci: false,
+        synthetic_usb_hid: false,
+        synthetic_usb_hid_on_xhci: false,
+    };
+}
+
+/// virtio-input device set (the tablet is only exposed alongside the keyboard/mouse pair).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VirtioInputKind {
+    #[default]
+    None,
+    /// virtio-input keyboard + relative mouse.
+    KeyboardMouse,
+    /// virtio-input keyboard + relative mouse + absolute tablet.
+    KeyboardMouseTablet,
+}
+
+/// Builder for [`MachineConfig`]; see the [module docs](self).
+///
+/// Starts from [`MachineConfig::default`] (or any preset via [`MachineConfigBuilder::from`]) and
+/// only touches the fields owned by each `with_*` call.
+#[derive(Debug, Clone)]
+pub struct MachineConfigBuilder {
+    cfg: MachineConfig,
+}
+
+impl MachineConfigBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::from(MachineConfig::default())
+    }
+
+    #[must_use]
+    pub fn with_ram_size_bytes(mut self, ram_size_bytes: u64) -> Self {
+        self.cfg.ram_size_bytes = ram_size_bytes;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cpu_count(mut self, cpu_count: u8) -> Self {
+        self.cfg.cpu_count = cpu_count;
+        self
+    }
+
+    /// Publish a NUMA topology via ACPI `SRAT`/`SLIT` (see [`MachineConfig::numa_nodes`]).
+    #[must_use]
+    pub fn with_numa_nodes(mut self, nodes: Vec<NumaNodeConfig>) -> Self {
+        self.cfg.numa_nodes = Some(nodes);
+        self
+    }
+
+    #[must_use]
+    pub fn with_boot_device(mut self, boot_device: BootDevice) -> Self {
+        self.cfg.boot_device = boot_device;
+        self
+    }
+
+    /// Enable or disable the PC platform (PIC/APIC/PIT/RTC/PCI/ACPI PM/HPET).
+    ///
+    /// PCI devices selected through the other `with_*` methods require it; [`Self::build`]
+    /// reports the first device that is missing its platform.
+    #[must_use]
+    pub fn with_pc_platform(mut self, enabled: bool) -> Self {
+        self.cfg.enable_pc_platform = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn with_acpi(mut self, enabled: bool) -> Self {
+        self.cfg.enable_acpi = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn with_nic(mut self, nic: NicKind) -> Self {
+        let cfg = &mut self.cfg;
+        cfg.enable_e1000 = false;
+        cfg.e1000_mac_addr = None;
+        cfg.enable_virtio_net = false;
+        cfg.virtio_net_mac_addr = None;
+        match nic {
+            NicKind::None => {}
+            NicKind::E1000 { mac_addr } => {
+                cfg.enable_e1000 = true;
+                cfg.e1000_mac_addr = mac_addr;
+            }
+            NicKind::VirtioNet { mac_addr } => {
+                cfg.enable_virtio_net = true;
+                cfg.virtio_net_mac_addr = mac_addr;
+            }
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn with_gpu(mut self, gpu: GpuKind) -> Self {
+        self.cfg.enable_vga = gpu == GpuKind::Vga;
+        self.cfg.enable_aerogpu = gpu == GpuKind::AeroGpu;
+        self
+    }
+
+    #[must_use]
+    pub fn with_storage(mut self, storage: StorageTopology) -> Self {
+        let StorageTopology {
+            ahci,
+            ide,
+            nvme,
+            virtio_blk,
+        } = storage;
+        self.cfg.enable_ahci = ahci;
+        self.cfg.enable_ide = ide;
+        self.cfg.enable_nvme = nvme;
+        self.cfg.enable_virtio_blk = virtio_blk;
+        self
+    }
+
+    #[must_use]
+    pub fn with_usb(mut self, usb: UsbControllers) -> Self {
+        let UsbControllers {
+            uhci,
+            ehci,
+            xhci,
+            synthetic_usb_hid,
+            synthetic_usb_hid_on_xhci,
+        } = usb;
+        self.cfg.enable_uhci = uhci;
+        self.cfg.enable_ehci = ehci;
+        self.cfg.enable_xhci = xhci;
+        self.cfg.enable_synthetic_usb_hid = synthetic_usb_hid;
+        self.cfg.synthetic_usb_hid_on_xhci = synthetic_usb_hid_on_xhci;
+        self
+    }
+
+    #[must_use]
+    pub fn with_virtio_input(mut self, input: VirtioInputKind) -> Self {
+        self.cfg.enable_virtio_input = input != VirtioInputKind::None;
+        self.cfg.enable_virtio_input_tablet = input == VirtioInputKind::KeyboardMouseTablet;
+        self
+    }
+
+    /// Validate and return the configuration.
+    ///
+    /// Runs the same checks as [`Machine::new`] and returns the same [`MachineError`]s.
+    pub fn build(self) -> Result<MachineConfig, MachineError> {
+        Machine::validate_cfg(&self.cfg)?;
+        Ok(self.cfg)
+    }
+}
+
+impl Default for MachineConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<MachineConfig> for MachineConfigBuilder {
+    fn from(cfg: MachineConfig) -> Self {
+        Self { cfg }
+    }
+}
+
+/// One field that differs between two [`MachineConfig`]s (see [`MachineConfig::diff`]).
+///
+/// Values are rendered with `{:?}` so deltas can be compared against golden lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDelta {
+    /// `MachineConfig` field name.
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+impl ConfigDelta {
+    pub fn new(field: &'static str, before: impl fmt::Debug, after: impl fmt::Debug) -> Self {
+        Self {
+            field,
+            before: format!("{before:?}"),
+            after: format!("{after:?}"),
+        }
+    }
+}
+
+impl fmt::Display for ConfigDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.before, self.after)
+    }
+}
+
+// The destructuring pattern is exhaustive, so adding a `MachineConfig` field without listing it
+// here is a compile error rather than a silently missing delta.
+macro_rules! config_diff {
+    ($before:expr, $after:expr; $($field:ident),* $(,)?) => {{
+        let MachineConfig { $($field),* } = $before;
+        let mut deltas = Vec::new();
+        $(
+            if *$field != $after.$field {
+                deltas.push(ConfigDelta::new(stringify!($field), $field, &$after.$field));
+            }
+        )*
+        deltas
+    }};
+}
+
+impl MachineConfig {
+    /// Start a [`MachineConfigBuilder`] from [`MachineConfig::default`].
+    #[must_use]
+    pub fn builder() -> MachineConfigBuilder {
+        MachineConfigBuilder::new()
+    }
+
+    /// Fields whose values differ from `self` in `other`, in declaration order.
+    ///
+    /// `before` holds the value from `self`, `after` the value from `other`.
+    pub fn diff(&self, other: &MachineConfig) -> Vec<ConfigDelta> {
+        config_diff!(self, other;
+            ram_size_bytes,
+            boot_drive,
+            cpu_count,
+            numa_nodes,
+            boot_device,
+            boot_order,
+            smbios_uuid_seed,
+            smbios,
+            enable_pc_platform,
+            enable_acpi,
+            enable_ahci,
+            enable_nvme,
+            enable_ide,
+            enable_virtio_blk,
+            virtio_blk_num_queues,
+            virtio_blk_transport,
+            enable_virtio_input,
+            enable_virtio_input_tablet,
+            virtio_input_transport,
+            enable_virtio_balloon,
+            virtio_balloon_transport,
+            enable_virtio_9p,
+            virtio_9p_read_only,
+            virtio_9p_mount_tag,
+            virtio_9p_transport,
+            enable_virtio_rng,
+            rng_seed,
+            virtio_rng_transport,
+            enable_uhci,
+            enable_ehci,
+            enable_xhci,
+            enable_synthetic_usb_hid,
+            synthetic_usb_hid_on_xhci,
+            enable_vga,
+            vga_lfb_base,
+            vga_lfb_offset,
+            vga_vram_bar_base,
+            vga_vram_size_bytes,
+            enable_aerogpu,
+            aerogpu_vram_size_bytes,
+            enable_serial,
+            enable_com2,
+            enable_com3,
+            enable_com4,
+            serial_irqs,
+            enable_debugcon,
+            enable_aero_bench,
+            enable_i8042,
+            enable_a20_gate,
+            enable_reset_ctrl,
+            enable_e1000,
+            e1000_mac_addr,
+            enable_virtio_net,
+            virtio_net_mac_addr,
+            virtio_net_transport,
+            enable_tpm,
+            enable_second_ioapic,
+            pci_pirq_to_gsi,
+            acpi_rtc_resync_event,
+            initial_nvram,
+            pointer_coalescing,
+            pointer_queue_full_policy,
+        )
+    }
+}
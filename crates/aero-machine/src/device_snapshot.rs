@@ -0,0 +1,121 @@
+//! Per-device snapshot introspection (see [`crate::Machine::device_snapshot_manifest`],
+//! [`crate::Machine::save_device_state`] and [`crate::Machine::load_device_state`]).
+//!
+//! Device blobs are exchanged in the same framing the `DEVICES` section of a full snapshot uses
+//! (`aero_snapshot::DeviceState::encode`: outer id, version, flags, length, payload), so a blob
+//! saved here is self-describing and can be checked into the repo as a compatibility fixture.
+//! Most devices embed an `aero-io-snapshot` TLV blob as payload; for those the manifest also
+//! reports the inner 4CC.
+
+use std::io::Cursor;
+
+use aero_io_snapshot::io::state::{SnapshotError as IoSnapshotError, SnapshotReader};
+use aero_snapshot as snapshot;
+
+const IO_SNAPSHOT_MAGIC: &[u8; 4] = b"AERO";
+
+/// One device entry of a machine snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSnapshotInfo {
+    /// Outer `DEVICES` section id.
+    pub id: snapshot::DeviceId,
+    /// Human-readable name of `id` (`None` for ids this build does not know).
+    pub name: Option<&'static str>,
+    /// Device state version (the major version for `aero-io-snapshot` payloads).
+    pub version: u16,
+    /// Device state flags (the minor version for `aero-io-snapshot` payloads).
+    pub flags: u16,
+    /// 4CC of the embedded `aero-io-snapshot` blob, for devices that use that encoding.
+    pub io_device_id: Option<[u8; 4]>,
+    /// Size of the current state payload in bytes.
+    pub size_bytes: usize,
+}
+
+impl DeviceSnapshotInfo {
+    pub(crate) fn from_state(state: &snapshot::DeviceState) -> Self {
+        Self {
+            id: state.id,
+            name: state.id.name(),
+            version: state.version,
+            flags: state.flags,
+            io_device_id: io_device_id(&state.data),
+            size_bytes: state.data.len(),
+        }
+    }
+}
+
+/// 4CC of an `aero-io-snapshot` payload, or `None` if `data` is not one.
+pub(crate) fn io_device_id(data: &[u8]) -> Option<[u8; 4]> {
+    if data.len() < 12 || &data[0..4] != IO_SNAPSHOT_MAGIC {
+        return None;
+    }
+    Some([data[8], data[9], data[10], data[11]])
+}
+
+pub(crate) fn encode_states(states: &[&snapshot::DeviceState]) -> snapshot::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for state in states {
+        state.encode(&mut out)?;
+    }
+    Ok(out)
+}
+
+/// Decode one or more framed entries, all of which must carry the outer id `id`.
+pub(crate) fn decode_states(
+    id: snapshot::DeviceId,
+    bytes: &[u8],
+) -> snapshot::Result<Vec<snapshot::DeviceState>> {
+    let mut r = Cursor::new(bytes);
+    let mut states = Vec::new();
+    while (r.position() as usize) < bytes.len() {
+        let state = snapshot::DeviceState::decode(&mut r, snapshot::limits::MAX_DEVICE_ENTRY_LEN)?;
+        if state.id != id {
+            return Err(snapshot::SnapshotError::Corrupt("device state id mismatch"));
+        }
+        states.push(state);
+    }
+    if states.is_empty() {
+        return Err(snapshot::SnapshotError::Corrupt("empty device state"));
+    }
+    Ok(states)
+}
+
+/// Check an incoming `aero-io-snapshot` payload against the state the live device produces.
+///
+/// Machine restore applies io-snapshot payloads best-effort, so without this a blob with the wrong
+/// 4CC, an unsupported major version or a malformed TLV stream would be silently ignored.
+pub(crate) fn validate_io_payload(
+    incoming: &snapshot::DeviceState,
+    current: &snapshot::DeviceState,
+) -> snapshot::Result<()> {
+    let Some(expected) = io_device_id(&current.data) else {
+        return Ok(());
+    };
+    let reader = SnapshotReader::parse(&incoming.data, expected).map_err(io_error)?;
+    let found = reader.header().device_version;
+    if found.major != current.version {
+        return Err(io_error(IoSnapshotError::UnsupportedDeviceMajorVersion {
+            found: found.major,
+            supported: current.version,
+        }));
+    }
+    if incoming.version != found.major || incoming.flags != found.minor {
+        return Err(snapshot::SnapshotError::Corrupt(
+            "device state version/flags do not match io-snapshot header",
+        ));
+    }
+    Ok(())
+}
+
+fn io_error(err: IoSnapshotError) -> snapshot::SnapshotError {
+    match err {
+        IoSnapshotError::DeviceIdMismatch { .. } => {
+            snapshot::SnapshotError::Corrupt("io-snapshot device id mismatch")
+        }
+        IoSnapshotError::UnsupportedFormatVersion { .. }
+        | IoSnapshotError::UnsupportedDeviceMajorVersion { .. } => {
+            snapshot::SnapshotError::Corrupt("unsupported io-snapshot version")
+        }
+        _ => snapshot::SnapshotError::Corrupt("malformed io-snapshot payload"),
+    }
+}
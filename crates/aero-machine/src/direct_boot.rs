@@ -0,0 +1,209 @@
+//! Firmware-less "direct" boot entry (see [`crate::Machine::boot_direct`]).
+//!
+//! Direct boot skips BIOS POST entirely and enters a flat payload in real, 32-bit protected or
+//! 64-bit long mode, similar to QEMU's `-kernel` style boot. The machine writes a small flat GDT
+//! and (optionally) identity-mapping page tables into guest RAM, loads the caller's blobs and then
+//! starts the BSP at the requested entry point.
+
+use std::fmt;
+
+use aero_cpu_core::state::{GPR_COUNT, RFLAGS_RESERVED1};
+
+/// GDT selector of the flat 32-bit code segment written by direct boot.
+pub const DIRECT_BOOT_CODE32_SELECTOR: u16 = 0x08;
+/// GDT selector of the flat data segment (loaded into DS/ES/FS/GS/SS) written by direct boot.
+pub const DIRECT_BOOT_DATA_SELECTOR: u16 = 0x10;
+/// GDT selector of the 64-bit code segment written by direct boot.
+pub const DIRECT_BOOT_CODE64_SELECTOR: u16 = 0x18;
+
+/// Flat GDT written at [`DirectBootSpec::gdt_paddr`]: null, code32, data, code64.
+pub(crate) const DIRECT_BOOT_GDT: [u64; 4] = [
+    0,
+    0x00CF_9B00_0000_FFFF,
+    0x00CF_9300_0000_FFFF,
+    0x00AF_9B00_0000_FFFF,
+];
+
+const PAGE_SIZE: u64 = 0x1000;
+const PTE_PRESENT: u64 = 1 << 0;
+const PTE_WRITABLE: u64 = 1 << 1;
+const PDE_LARGE_PAGE: u64 = 1 << 7;
+
+/// CPU mode the BSP starts executing the payload in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectBootMode {
+    /// 16-bit real mode; the entry point must lie below 1MiB.
+    Real,
+    /// 32-bit protected mode with flat segments.
+    Protected32,
+    /// 64-bit long mode; requires paging.
+    Long64,
+}
+
+/// Paging configuration for a direct boot entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectBootPaging {
+    /// Paging disabled (not valid for [`DirectBootMode::Long64`]).
+    Disabled,
+    /// Caller-provided page tables (e.g. loaded via [`DirectBootSpec::blobs`]) rooted at `cr3`.
+    ///
+    /// In [`DirectBootMode::Protected32`] these are legacy 32-bit (non-PAE) tables; in
+    /// [`DirectBootMode::Long64`] they are 4-level tables.
+    Provided { cr3: u64 },
+    /// Identity-map the first `gib` GiB of the physical address space using 2MiB pages.
+    ///
+    /// The machine generates the tables at the 4KiB-aligned `tables_paddr`, using
+    /// `(gib + 2) * 4KiB` bytes in long mode and `(gib + 1) * 4KiB` bytes (PAE, at most 4GiB) in
+    /// 32-bit protected mode.
+    IdentityMap { gib: u32, tables_paddr: u64 },
+}
+
+/// Description of a direct boot entry; see [`crate::Machine::boot_direct`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectBootSpec {
+    pub mode: DirectBootMode,
+    /// Entry point linear address.
+    ///
+    /// In real mode this is split into `CS = entry >> 4`, `IP = entry & 0xF`.
+    pub entry: u64,
+    /// Initial general purpose registers, indexed by [`aero_cpu_core::state::gpr`].
+    pub gpr: [u64; GPR_COUNT],
+    /// Initial RFLAGS (the reserved bit 1 is always set).
+    pub rflags: u64,
+    pub paging: DirectBootPaging,
+    /// Guest-physical address the flat GDT is written to (unused in real mode).
+    pub gdt_paddr: u64,
+    /// `(paddr, bytes)` blobs loaded into guest RAM after the GDT and page tables are written.
+    pub blobs: Vec<(u64, Vec<u8>)>,
+}
+
+impl Default for DirectBootSpec {
+    fn default() -> Self {
+        Self {
+            mode: DirectBootMode::Protected32,
+            entry: 0x0010_0000,
+            gpr: [0; GPR_COUNT],
+            rflags: RFLAGS_RESERVED1,
+            paging: DirectBootPaging::Disabled,
+            gdt_paddr: 0x0800,
+            blobs: Vec::new(),
+        }
+    }
+}
+
+/// Errors returned by [`crate::Machine::boot_direct`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectBootError {
+    /// The entry point does not lie in guest RAM (or, in real mode, below 1MiB).
+    EntryOutsideRam(u64),
+    BlobOutsideRam {
+        paddr: u64,
+        len: u64,
+    },
+    GdtOutsideRam(u64),
+    PageTablesOutsideRam(u64),
+    /// Identity-map page tables must be 4KiB aligned.
+    MisalignedPageTables(u64),
+    /// Identity-map size is zero or too large for the selected mode.
+    InvalidIdentityMapSize(u32),
+    LongModeRequiresPaging,
+    RealModePagingUnsupported,
+}
+
+impl fmt::Display for DirectBootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectBootError::EntryOutsideRam(entry) => {
+                write!(f, "direct boot entry point {entry:#x} is not in guest RAM")
+            }
+            DirectBootError::BlobOutsideRam { paddr, len } => write!(
+                f,
+                "direct boot blob at {paddr:#x} (len {len:#x}) does not fit in guest RAM"
+            ),
+            DirectBootError::GdtOutsideRam(paddr) => {
+                write!(f, "direct boot GDT at {paddr:#x} does not fit in guest RAM")
+            }
+            DirectBootError::PageTablesOutsideRam(paddr) => write!(
+                f,
+                "direct boot page tables at {paddr:#x} do not fit in guest RAM"
+            ),
+            DirectBootError::MisalignedPageTables(paddr) => write!(
+                f,
+                "direct boot page tables at {paddr:#x} are not 4KiB aligned"
+            ),
+            DirectBootError::InvalidIdentityMapSize(gib) => write!(
+                f,
+                "invalid direct boot identity map size {gib}GiB for the selected mode"
+            ),
+            DirectBootError::LongModeRequiresPaging => {
+                write!(f, "direct boot into long mode requires page tables")
+            }
+            DirectBootError::RealModePagingUnsupported => {
+                write!(f, "direct boot into real mode cannot enable paging")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DirectBootError {}
+
+/// Guest-physical writes making up a generated identity map, plus the resulting CR3.
+pub(crate) struct IdentityMapTables {
+    pub(crate) cr3: u64,
+    pub(crate) entries: Vec<(u64, u64)>,
+    pub(crate) len: u64,
+}
+
+/// Build identity-mapping tables for the first `gib` GiB using 2MiB pages.
+///
+/// Long mode uses PML4 -> PDPT -> one PD per GiB; 32-bit protected mode uses a PAE PDPT -> one PD
+/// per GiB. Only non-zero entries are returned; the caller zeroes the table pages first.
+pub(crate) fn identity_map_tables(
+    mode: DirectBootMode,
+    gib: u32,
+    tables_paddr: u64,
+) -> Result<IdentityMapTables, DirectBootError> {
+    let max_gib = match mode {
+        DirectBootMode::Long64 => 512,
+        DirectBootMode::Protected32 => 4,
+        DirectBootMode::Real => return Err(DirectBootError::RealModePagingUnsupported),
+    };
+    if gib == 0 || gib > max_gib {
+        return Err(DirectBootError::InvalidIdentityMapSize(gib));
+    }
+    if !tables_paddr.is_multiple_of(PAGE_SIZE) {
+        return Err(DirectBootError::MisalignedPageTables(tables_paddr));
+    }
+
+    let mut entries = Vec::new();
+    let (pdpt, first_pd) = match mode {
+        DirectBootMode::Long64 => {
+            let pdpt = tables_paddr + PAGE_SIZE;
+            entries.push((tables_paddr, pdpt | PTE_PRESENT | PTE_WRITABLE));
+            (pdpt, pdpt + PAGE_SIZE)
+        }
+        _ => (tables_paddr, tables_paddr + PAGE_SIZE),
+    };
+    // PAE PDPTEs have no R/W bit (it is reserved); long-mode PDPTEs do.
+    let pdpte_flags = match mode {
+        DirectBootMode::Long64 => PTE_PRESENT | PTE_WRITABLE,
+        _ => PTE_PRESENT,
+    };
+    for i in 0..u64::from(gib) {
+        let pd = first_pd + i * PAGE_SIZE;
+        entries.push((pdpt + i * 8, pd | pdpte_flags));
+        for j in 0..512u64 {
+            let frame = (i << 30) | (j << 21);
+            entries.push((
+                pd + j * 8,
+                frame | PTE_PRESENT | PTE_WRITABLE | PDE_LARGE_PAGE,
+            ));
+        }
+    }
+
+    Ok(IdentityMapTables {
+        cr3: tables_paddr,
+        entries,
+        len: first_pd + u64::from(gib) * PAGE_SIZE - tables_paddr,
+    })
+}
@@ -0,0 +1,121 @@
+//! Guest dirty-page rate sampling (see [`crate::Machine::start_dirty_sampling`]).
+//!
+//! Sampling reads the sampling bitmap of the machine's [`memory::DirtyTracker`], which is fed by
+//! the same RAM writes as the snapshot dirty set but drained separately. Harvesting a sample
+//! therefore never removes pages from the next dirty snapshot, and taking a snapshot never empties
+//! the current sampling interval.
+//!
+//! Samples are harvested at `run_slice` boundaries once at least one interval of guest time has
+//! elapsed, so an interval may run long by up to one slice; rates use the measured elapsed time.
+
+use std::collections::VecDeque;
+
+/// Number of completed sampling intervals retained for [`DirtyRateStats`].
+pub const DIRTY_SAMPLING_HISTORY: usize = 16;
+
+/// Guest memory write activity over the retained sampling intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRateStats {
+    /// Configured sampling interval in guest nanoseconds.
+    pub interval_ns: u64,
+    /// Completed intervals the statistics cover (at most [`DIRTY_SAMPLING_HISTORY`]).
+    pub intervals: usize,
+    /// Pages dirtied during the most recent completed interval.
+    pub last_interval_pages: u64,
+    /// Dirtied pages per second of guest time, summed over the retained intervals.
+    pub pages_per_sec: u64,
+    /// Distinct pages dirtied across the retained intervals.
+    pub working_set_pages: u64,
+    /// `working_set_pages` in bytes.
+    pub working_set_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+struct IntervalSample {
+    bitmap: Vec<u64>,
+    pages: u64,
+    elapsed_ns: u64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DirtySampler {
+    interval_ns: u64,
+    // Guest time the current interval started at; `None` takes a fresh baseline (discarding the
+    // harvested bits) at the next boundary, after starting, reset or snapshot restore.
+    interval_start_ns: Option<u64>,
+    history: VecDeque<IntervalSample>,
+}
+
+impl DirtySampler {
+    pub(crate) fn new(interval_ns: u64) -> Self {
+        Self {
+            interval_ns,
+            interval_start_ns: None,
+            history: VecDeque::with_capacity(DIRTY_SAMPLING_HISTORY),
+        }
+    }
+
+    /// Drop the history and restart measuring at the next boundary.
+    pub(crate) fn rearm(&mut self) {
+        self.interval_start_ns = None;
+        self.history.clear();
+    }
+
+    /// Whether the sampling bitmap should be harvested at guest time `now_ns`.
+    pub(crate) fn due(&self, now_ns: u64) -> bool {
+        self.interval_start_ns
+            .is_none_or(|start| now_ns.saturating_sub(start) >= self.interval_ns)
+    }
+
+    /// Close the current interval at `now_ns` with the pages dirtied during it.
+    pub(crate) fn record(&mut self, now_ns: u64, bitmap: Vec<u64>) {
+        let Some(start) = self.interval_start_ns.replace(now_ns) else {
+            return;
+        };
+        if self.history.len() == DIRTY_SAMPLING_HISTORY {
+            self.history.pop_front();
+        }
+        let pages = bitmap.iter().map(|w| u64::from(w.count_ones())).sum();
+        self.history.push_back(IntervalSample {
+            bitmap,
+            pages,
+            elapsed_ns: now_ns.saturating_sub(start),
+        });
+    }
+
+    pub(crate) fn stats(&self, page_size: u32) -> DirtyRateStats {
+        let total_pages: u64 = self.history.iter().map(|s| s.pages).sum();
+        let total_ns: u64 = self.history.iter().map(|s| s.elapsed_ns).sum();
+        let pages_per_sec = if total_ns == 0 {
+            0
+        } else {
+            u64::try_from(u128::from(total_pages) * 1_000_000_000 / u128::from(total_ns))
+                .unwrap_or(u64::MAX)
+        };
+
+        let words = self
+            .history
+            .iter()
+            .map(|s| s.bitmap.len())
+            .max()
+            .unwrap_or(0);
+        let working_set_pages = (0..words)
+            .map(|i| {
+                let union = self
+                    .history
+                    .iter()
+                    .fold(0u64, |acc, s| acc | s.bitmap.get(i).copied().unwrap_or(0));
+                u64::from(union.count_ones())
+            })
+            .sum::<u64>();
+
+        DirtyRateStats {
+            interval_ns: self.interval_ns,
+            intervals: self.history.len(),
+            last_interval_pages: self.history.back().map_or(0, |s| s.pages),
+            pages_per_sec,
+            working_set_pages,
+            working_set_bytes: working_set_pages.saturating_mul(u64::from(page_size)),
+        }
+    }
+}
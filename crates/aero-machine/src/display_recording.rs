@@ -0,0 +1,167 @@
+//! Guest display recording (see [`crate::Machine::start_display_recording`]).
+//!
+//! Frames are captured from the host-visible framebuffer cache right after
+//! [`crate::Machine::display_present`] has produced it, so recording never renders (or copies) the
+//! full-resolution frame a second time: the optional downscale reads the cached RGBA8888 pixels
+//! directly. Encoding is left to the host; the recorder only hands out timed raw frames.
+
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+/// Display recording parameters (see [`crate::Machine::start_display_recording`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayRecordingConfig {
+    /// Frames buffered until the host takes them; once full, the oldest frame is evicted.
+    pub max_frames: usize,
+    /// Minimum guest time between two recorded frames, in nanoseconds.
+    pub min_frame_interval_guest_ns: u64,
+    /// Output `(width, height)` of every recorded frame, or `None` to keep the display resolution.
+    pub scale: Option<(u32, u32)>,
+}
+
+/// One recorded display frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Guest time at which the frame was recorded, in nanoseconds.
+    pub guest_time_ns: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Row-major pixels in the [`crate::Machine::display_framebuffer`] format (RGBA8888).
+    pub pixels: Vec<u32>,
+}
+
+/// Frames returned by [`crate::Machine::take_recorded_frames`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedFrames {
+    /// Recorded frames, oldest first.
+    pub frames: Vec<RecordedFrame>,
+    /// Frames evicted because the queue was full since the previous take.
+    pub dropped_frames: u64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DisplayRecorder {
+    config: DisplayRecordingConfig,
+    frames: VecDeque<RecordedFrame>,
+    dropped_frames: u64,
+    // Content hash of the last recorded frame, used to skip identical consecutive frames.
+    last_hash: Option<u64>,
+    last_frame_ns: Option<u64>,
+    // Content hash of a presented frame that differs from the last recorded one but was held back
+    // by the frame interval; it is recorded by the first present once the interval has elapsed.
+    pending_hash: Option<u64>,
+}
+
+impl DisplayRecorder {
+    pub(crate) fn new(config: DisplayRecordingConfig) -> Self {
+        Self {
+            config,
+            frames: VecDeque::with_capacity(config.max_frames.min(64)),
+            dropped_frames: 0,
+            last_hash: None,
+            last_frame_ns: None,
+            pending_hash: None,
+        }
+    }
+
+    /// Offer the cached framebuffer after a present at guest time `now_ns`. `rendered` tells
+    /// whether the present produced a new frame (otherwise only a held-back frame can be due).
+    pub(crate) fn offer(
+        &mut self,
+        now_ns: u64,
+        rendered: bool,
+        width: u32,
+        height: u32,
+        pixels: &[u32],
+    ) {
+        if rendered {
+            let hash = frame_hash(width, height, pixels);
+            self.pending_hash = (self.last_hash != Some(hash)).then_some(hash);
+        }
+        let Some(hash) = self.pending_hash else {
+            return;
+        };
+        if self.last_frame_ns.is_some_and(|last| {
+            now_ns.saturating_sub(last) < self.config.min_frame_interval_guest_ns
+        }) {
+            return;
+        }
+
+        let (out_width, out_height) = self.config.scale.unwrap_or((width, height));
+        let pixels = if (out_width, out_height) == (width, height) {
+            pixels.to_vec()
+        } else {
+            box_downscale(width, height, pixels, out_width, out_height)
+        };
+        if self.frames.len() == self.config.max_frames {
+            self.frames.pop_front();
+            self.dropped_frames += 1;
+        }
+        self.frames.push_back(RecordedFrame {
+            guest_time_ns: now_ns,
+            width: out_width,
+            height: out_height,
+            pixels,
+        });
+        self.last_hash = Some(hash);
+        self.last_frame_ns = Some(now_ns);
+        self.pending_hash = None;
+    }
+
+    pub(crate) fn take(&mut self) -> RecordedFrames {
+        RecordedFrames {
+            frames: self.frames.drain(..).collect(),
+            dropped_frames: std::mem::take(&mut self.dropped_frames),
+        }
+    }
+}
+
+fn frame_hash(width: u32, height: u32, pixels: &[u32]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (width, height).hash(&mut hasher);
+    pixels.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resample `pixels` to `out_width` x `out_height`, averaging every channel over the source box
+/// covered by each output pixel. An empty source produces a black frame.
+fn box_downscale(
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+    out_width: u32,
+    out_height: u32,
+) -> Vec<u32> {
+    let out_len = out_width as usize * out_height as usize;
+    if width == 0 || height == 0 || pixels.len() < width as usize * height as usize {
+        return vec![0; out_len];
+    }
+    // Source span `[start, end)` covered by output index `i` (at least one source pixel, so
+    // upscaling degrades to nearest-neighbour).
+    let span = |i: u32, src: u32, out: u32| {
+        let start = (u64::from(i) * u64::from(src) / u64::from(out)) as usize;
+        let end = (u64::from(i + 1) * u64::from(src) / u64::from(out)) as usize;
+        (start, end.max(start + 1))
+    };
+
+    let mut out = Vec::with_capacity(out_len);
+    for oy in 0..out_height {
+        let (y0, y1) = span(oy, height, out_height);
+        for ox in 0..out_width {
+            let (x0, x1) = span(ox, width, out_width);
+            let mut sums = [0u64; 4];
+            for y in y0..y1 {
+                let row = &pixels[y * width as usize..][x0..x1];
+                for px in row {
+                    for (sum, byte) in sums.iter_mut().zip(px.to_le_bytes()) {
+                        *sum += u64::from(byte);
+                    }
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u64;
+            let avg = sums.map(|sum| ((sum + count / 2) / count) as u8);
+            out.push(u32::from_le_bytes(avg));
+        }
+    }
+    out
+}
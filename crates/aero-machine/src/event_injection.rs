@@ -0,0 +1,173 @@
+//! Host-driven architectural event injection for fault-injection testing (see
+//! [`crate::Machine::inject_nmi`] and [`crate::Machine::inject_exception`]).
+//!
+//! Events are queued per vCPU and moved into the core's pending-event state at the next
+//! instruction boundary of `run_slice`. Queued events are host test state: they are not
+//! snapshotted and are dropped on reset and snapshot restore.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use aero_cpu_core::exceptions::Exception;
+use aero_cpu_core::CpuCore;
+
+/// Maximum number of injected events queued per vCPU.
+pub const MAX_INJECTED_EVENTS: usize = 64;
+
+/// Errors returned by [`crate::Machine::inject_nmi`] and [`crate::Machine::inject_exception`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InjectEventError {
+    /// The vCPU index is not below [`crate::Machine::cpu_count`].
+    InvalidCpuIndex(usize),
+    /// The vector is reserved by the architecture (9, 15, 22..=31).
+    ReservedVector(u8),
+    /// [`MAX_INJECTED_EVENTS`] events are already queued for the vCPU.
+    QueueFull(usize),
+}
+
+impl fmt::Display for InjectEventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InjectEventError::InvalidCpuIndex(index) => {
+                write!(f, "cannot inject event: vCPU {index} does not exist")
+            }
+            InjectEventError::ReservedVector(vector) => {
+                write!(f, "cannot inject reserved exception vector {vector}")
+            }
+            InjectEventError::QueueFull(index) => write!(
+                f,
+                "cannot inject event: {MAX_INJECTED_EVENTS} events already queued for vCPU {index}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InjectEventError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InjectedEvent {
+    Nmi,
+    Exception {
+        exception: Exception,
+        error_code: Option<u32>,
+    },
+    /// A non-exception vector, delivered like an external interrupt (subject to `IF`).
+    Interrupt(u8),
+}
+
+impl InjectedEvent {
+    /// Classify a host-requested vector.
+    pub(crate) fn for_vector(
+        vector: u8,
+        error_code: Option<u32>,
+    ) -> Result<Self, InjectEventError> {
+        if vector >= 32 {
+            return Ok(Self::Interrupt(vector));
+        }
+        match Exception::from_vector(vector) {
+            Some(Exception::NonMaskableInterrupt) => Ok(Self::Nmi),
+            Some(exception) => Ok(Self::Exception {
+                exception,
+                error_code,
+            }),
+            None => Err(InjectEventError::ReservedVector(vector)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct EventInjector {
+    queues: Vec<VecDeque<InjectedEvent>>,
+    smi_pending: bool,
+}
+
+impl EventInjector {
+    pub(crate) fn new(cpu_count: usize) -> Self {
+        Self {
+            queues: vec![VecDeque::new(); cpu_count],
+            smi_pending: false,
+        }
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        cpu_index: usize,
+        event: InjectedEvent,
+    ) -> Result<(), InjectEventError> {
+        let queue = self
+            .queues
+            .get_mut(cpu_index)
+            .ok_or(InjectEventError::InvalidCpuIndex(cpu_index))?;
+        if queue.len() >= MAX_INJECTED_EVENTS {
+            return Err(InjectEventError::QueueFull(cpu_index));
+        }
+        queue.push_back(event);
+        Ok(())
+    }
+
+    pub(crate) fn has_queued(&self, cpu_index: usize) -> bool {
+        self.queues
+            .get(cpu_index)
+            .is_some_and(|queue| !queue.is_empty())
+    }
+
+    pub(crate) fn request_smi(&mut self) {
+        self.smi_pending = true;
+    }
+
+    pub(crate) fn take_smi(&mut self) -> bool {
+        std::mem::take(&mut self.smi_pending)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.queues.iter_mut().for_each(VecDeque::clear);
+        self.smi_pending = false;
+    }
+
+    /// Move the next queued event for `cpu_index` into `cpu`'s pending state.
+    ///
+    /// NMIs and exceptions become the core's pending event (delivered ahead of external
+    /// interrupts regardless of `IF`) and wake the vCPU from `HLT`. Other vectors join the external
+    /// interrupt FIFO and follow the normal `IF`/interrupt-shadow rules. An event stays queued while
+    /// the core already has something pending in the corresponding slot.
+    ///
+    /// Returns the vector of the event that was handed to the core.
+    pub(crate) fn deliver(&mut self, cpu_index: usize, cpu: &mut CpuCore) -> Option<u8> {
+        let queue = self.queues.get_mut(cpu_index)?;
+        let vector = match *queue.front()? {
+            InjectedEvent::Nmi => {
+                Self::raise_exception(cpu, Exception::NonMaskableInterrupt, None)?
+            }
+            InjectedEvent::Exception {
+                exception,
+                error_code,
+            } => Self::raise_exception(cpu, exception, error_code)?,
+            InjectedEvent::Interrupt(vector) => {
+                if !cpu.pending.external_interrupts().is_empty() {
+                    return None;
+                }
+                cpu.pending.inject_external_interrupt(vector);
+                vector
+            }
+        };
+        queue.pop_front();
+        Some(vector)
+    }
+
+    fn raise_exception(
+        cpu: &mut CpuCore,
+        exception: Exception,
+        error_code: Option<u32>,
+    ) -> Option<u8> {
+        if cpu.pending.has_pending_event() {
+            return None;
+        }
+        // The event is taken at the current instruction boundary, so the saved RIP is the next
+        // instruction to execute (after `HLT` when the vCPU is halted).
+        let rip = cpu.state.rip();
+        cpu.pending
+            .raise_exception_fault(&mut cpu.state, exception, rip, error_code, None);
+        cpu.state.halted = false;
+        Some(exception.vector())
+    }
+}
@@ -0,0 +1,137 @@
+//! How guest-physical addresses map onto the linear guest RAM backing.
+//!
+//! Guest RAM is always backed by one contiguous `[0..ram_size_bytes)` buffer (a Rust allocation,
+//! a sparse backend, or host-provided wasm linear memory / `SharedArrayBuffer` passed to
+//! [`crate::Machine::new_with_guest_memory`]). Once `ram_size_bytes` exceeds the PCIe ECAM base
+//! the PC layout splits that buffer in two: the low part stays identity mapped below the ECAM/PCI
+//! hole and the rest is remapped above 4GiB.
+//!
+//! [`crate::Machine::guest_ram_layout`] publishes that split so a host that owns the backing
+//! buffer (e.g. a GPU worker scanning out a guest-RAM framebuffer) can translate guest-physical
+//! addresses itself. The machine uses the same table for its own dense RAM accesses (snapshots and
+//! the AeroGPU scanout readback), so both sides always agree.
+
+use memory::GuestMemory;
+
+use crate::FOUR_GIB;
+
+/// One guest-physical RAM window and where it lives in the linear RAM backing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamRegionDescriptor {
+    /// First guest-physical address of the window.
+    pub gpa_start: u64,
+    /// Length of the window in bytes.
+    pub len: u64,
+    /// Byte offset of `gpa_start` inside the RAM backing.
+    pub backing_offset: u64,
+}
+
+impl RamRegionDescriptor {
+    /// Guest-physical address one past the end of the window.
+    pub fn gpa_end(&self) -> u64 {
+        self.gpa_start + self.len
+    }
+
+    /// Translate `gpa` into a backing offset if it lies in this window.
+    pub fn backing_offset_of(&self, gpa: u64) -> Option<u64> {
+        let delta = gpa.checked_sub(self.gpa_start)?;
+        (delta < self.len).then(|| self.backing_offset + delta)
+    }
+}
+
+/// Guest RAM layout for a machine with `ram_size_bytes` of RAM, sorted by guest-physical address.
+pub(crate) fn layout(ram_size_bytes: u64) -> Vec<RamRegionDescriptor> {
+    let low_ram_end = firmware::bios::PCIE_ECAM_BASE;
+    let mut regions = Vec::with_capacity(2);
+    if ram_size_bytes > 0 {
+        regions.push(RamRegionDescriptor {
+            gpa_start: 0,
+            len: ram_size_bytes.min(low_ram_end),
+            backing_offset: 0,
+        });
+    }
+    if ram_size_bytes > low_ram_end {
+        regions.push(RamRegionDescriptor {
+            gpa_start: FOUR_GIB,
+            len: ram_size_bytes - low_ram_end,
+            backing_offset: low_ram_end,
+        });
+    }
+    regions
+}
+
+pub(crate) fn gpa_to_backing_offset(layout: &[RamRegionDescriptor], gpa: u64) -> Option<u64> {
+    layout
+        .iter()
+        .find_map(|region| region.backing_offset_of(gpa))
+}
+
+/// Translate `[gpa, gpa + len)` into a backing offset if the whole range lies in one window (and
+/// is therefore contiguous in the backing).
+pub(crate) fn gpa_range_to_backing_offset(
+    layout: &[RamRegionDescriptor],
+    gpa: u64,
+    len: u64,
+) -> Option<u64> {
+    let end = gpa.checked_add(len)?;
+    layout
+        .iter()
+        .find(|region| region.backing_offset_of(gpa).is_some() && end <= region.gpa_end())
+        .map(|region| region.backing_offset + (gpa - region.gpa_start))
+}
+
+/// Inverse of [`gpa_to_backing_offset`]: the guest-physical address of backing byte `offset` and
+/// the number of bytes that stay contiguous from there.
+pub(crate) fn backing_offset_to_gpa(
+    layout: &[RamRegionDescriptor],
+    offset: u64,
+) -> Option<(u64, u64)> {
+    layout.iter().find_map(|region| {
+        let delta = offset.checked_sub(region.backing_offset)?;
+        (delta < region.len).then(|| (region.gpa_start + delta, region.len - delta))
+    })
+}
+
+/// Read-only [`memory::MemoryBus`] over guest RAM only, resolving addresses through a
+/// [`RamRegionDescriptor`] layout. Anything outside RAM (MMIO, the PCI hole) reads as zero, so a
+/// scanout never re-enters device models.
+pub(crate) struct GuestRamReadbackBus<'a> {
+    ram: &'a dyn GuestMemory,
+    layout: &'a [RamRegionDescriptor],
+}
+
+impl<'a> GuestRamReadbackBus<'a> {
+    pub(crate) fn new(ram: &'a dyn GuestMemory, layout: &'a [RamRegionDescriptor]) -> Self {
+        Self { ram, layout }
+    }
+}
+
+impl memory::MemoryBus for GuestRamReadbackBus<'_> {
+    fn read_physical(&mut self, paddr: u64, buf: &mut [u8]) {
+        let mut gpa = paddr;
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            let Some(region) = self
+                .layout
+                .iter()
+                .find(|region| region.backing_offset_of(gpa).is_some())
+            else {
+                remaining.fill(0);
+                return;
+            };
+            let available = usize::try_from(region.gpa_end() - gpa).unwrap_or(usize::MAX);
+            let chunk_len = remaining.len().min(available);
+            let (chunk, rest) = std::mem::take(&mut remaining).split_at_mut(chunk_len);
+            // The RAM handed to the bus is already remapped into guest-physical space.
+            if self.ram.read_into(gpa, chunk).is_err() {
+                chunk.fill(0);
+            }
+            gpa += chunk_len as u64;
+            remaining = rest;
+        }
+    }
+
+    fn write_physical(&mut self, _paddr: u64, _buf: &[u8]) {
+        // Readback-only bus; ignore writes.
+    }
+}
@@ -0,0 +1,187 @@
+//! Input latency sampling (see [`crate::Machine::start_input_latency_sampling`]).
+//!
+//! Each `inject_input_batch` event that queues output in an input device yields one sample,
+//! stamped with the guest time the device received it. Deliveries are detected by comparing the
+//! devices' output counters around the event, so events a backend drops (or that only update host
+//! state) produce no sample. Pointer events held back by pointer coalescing are sampled when their
+//! frame reaches the device, carrying the host timestamp of the oldest held event.
+//!
+//! For PS/2 and virtio-input the sample also remembers the counter value of the event's last
+//! queued byte/event, and is completed once the guest consumed it: the byte was read from port
+//! 0x60, or the event was completed into the virtio-input used ring. Consumption is observed
+//! after each CPU batch and each virtio-input poll, so consume times have batch resolution.
+
+use std::collections::VecDeque;
+
+/// Number of samples retained until [`crate::Machine::take_input_latency_samples`] drains them.
+pub const INPUT_LATENCY_HISTORY: usize = 256;
+
+const BACKEND_COUNT: usize = 6;
+
+/// Device an input event was delivered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputBackend {
+    Ps2Keyboard,
+    Ps2Mouse,
+    VirtioKeyboard,
+    VirtioMouse,
+    VirtioTablet,
+    /// Any of the synthetic USB HID devices (keyboard, mouse, gamepad, consumer control).
+    Usb,
+}
+
+impl InputBackend {
+    const ALL: [InputBackend; BACKEND_COUNT] = [
+        InputBackend::Ps2Keyboard,
+        InputBackend::Ps2Mouse,
+        InputBackend::VirtioKeyboard,
+        InputBackend::VirtioMouse,
+        InputBackend::VirtioTablet,
+        InputBackend::Usb,
+    ];
+
+    /// Whether the guest's consumption of delivered events can be observed.
+    fn tracks_consumption(self) -> bool {
+        self != InputBackend::Usb
+    }
+}
+
+/// Timing of one host input event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLatencySample {
+    /// The event's host timestamp from the input batch (wrapping microseconds), or `None` if the
+    /// host left it 0.
+    pub host_ts: Option<u32>,
+    /// Guest time at which the event was queued into the backend device.
+    pub inject_guest_ns: u64,
+    /// Guest time at which the guest was observed to have consumed the event, or `None` for USB
+    /// backends (whose reports are polled by the host controller, not read by the guest).
+    pub consume_guest_ns: Option<u64>,
+    pub backend: InputBackend,
+}
+
+/// Output counters of every backend, indexed by `InputBackend as usize`: queued or consumed PS/2
+/// output bytes, virtio-input events, and (queued only) USB HID deliveries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct InputCounters([u64; BACKEND_COUNT]);
+
+impl InputCounters {
+    pub(crate) fn set(&mut self, backend: InputBackend, value: u64) {
+        self.0[backend as usize] = value;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    sample: InputLatencySample,
+    // Counter value the backend's consumed counter must reach to complete the sample.
+    awaiting: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct InputLatencyTracker {
+    entries: VecDeque<Entry>,
+    awaiting: usize,
+    // Host timestamp of the oldest event waiting in a pointer coalescing backlog.
+    held_pointer_ts: Option<Option<u32>>,
+}
+
+impl InputLatencyTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(INPUT_LATENCY_HISTORY),
+            awaiting: 0,
+            held_pointer_ts: None,
+        }
+    }
+
+    /// Whether any sample still waits for the guest to consume its event.
+    pub(crate) fn awaiting_consumption(&self) -> bool {
+        self.awaiting != 0
+    }
+
+    /// Record one sample per backend whose queued counter advanced from `before` to `after`.
+    pub(crate) fn record(
+        &mut self,
+        host_ts: Option<u32>,
+        now_ns: u64,
+        before: &InputCounters,
+        after: &InputCounters,
+    ) {
+        for backend in InputBackend::ALL {
+            let queued = after.0[backend as usize];
+            if queued == before.0[backend as usize] {
+                continue;
+            }
+            if self.entries.len() == INPUT_LATENCY_HISTORY {
+                if let Some(old) = self.entries.pop_front() {
+                    self.awaiting -= usize::from(old.awaiting.is_some());
+                }
+            }
+            let awaiting = backend.tracks_consumption().then_some(queued);
+            self.awaiting += usize::from(awaiting.is_some());
+            self.entries.push_back(Entry {
+                sample: InputLatencySample {
+                    host_ts,
+                    inject_guest_ns: now_ns,
+                    consume_guest_ns: None,
+                    backend,
+                },
+                awaiting,
+            });
+        }
+    }
+
+    /// Note that an event with `host_ts` is waiting in a pointer coalescing backlog.
+    pub(crate) fn hold_pointer(&mut self, host_ts: Option<u32>) {
+        self.held_pointer_ts.get_or_insert(host_ts);
+    }
+
+    /// Host timestamp for pointer frames flushed from the coalescing backlogs; `drained` forgets it
+    /// once no event is held anymore.
+    pub(crate) fn release_pointer(&mut self, drained: bool) -> Option<u32> {
+        let host_ts = self.held_pointer_ts.flatten();
+        if drained {
+            self.held_pointer_ts = None;
+        }
+        host_ts
+    }
+
+    /// Complete the samples whose events the guest has consumed according to `consumed`.
+    pub(crate) fn observe_consumed(&mut self, consumed: &InputCounters, now_ns: u64) {
+        for entry in self.entries.iter_mut() {
+            let Some(target) = entry.awaiting else {
+                continue;
+            };
+            if consumed.0[entry.sample.backend as usize] >= target {
+                entry.sample.consume_guest_ns = Some(now_ns);
+                entry.awaiting = None;
+                self.awaiting -= 1;
+            }
+        }
+    }
+
+    /// Stop waiting for consumption (device counters restart after reset and snapshot restore);
+    /// the affected samples keep `consume_guest_ns: None`.
+    pub(crate) fn abandon_pending(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.awaiting = None;
+        }
+        self.awaiting = 0;
+        self.held_pointer_ts = None;
+    }
+
+    /// Remove and return the samples that are complete, oldest first. Samples still waiting for
+    /// the guest to consume their event stay in the ring.
+    pub(crate) fn take_complete(&mut self) -> Vec<InputLatencySample> {
+        let mut out = Vec::with_capacity(self.entries.len() - self.awaiting);
+        self.entries.retain(|entry| {
+            if entry.awaiting.is_some() {
+                return true;
+            }
+            out.push(entry.sample);
+            false
+        });
+        out
+    }
+}
@@ -0,0 +1,208 @@
+//! Host-side kernel debugger bridge for a COM port (see [`crate::Machine::enable_kd_bridge`]).
+//!
+//! Windows KDCOM speaks the KD serial protocol over a 16550 UART. The bridge exposes the raw byte
+//! stream as timestamped chunks and can optionally delimit KD packets so hosts (e.g. a WebSocket
+//! relay to a debugger) can forward whole packets without tracking UART state themselves. No
+//! protocol interpretation beyond framing is performed: checksums, packet types and ids are passed
+//! through untouched.
+//!
+//! KD packet layout (little-endian):
+//!
+//! ```text
+//! leader: u32        0x30303030 (data) or 0x69696969 (control)
+//! packet_type: u16
+//! byte_count: u16
+//! packet_id: u32
+//! checksum: u32
+//! data: [u8; byte_count]
+//! trailer: u8        0xAA (data packets only)
+//! ```
+
+use std::collections::VecDeque;
+
+/// Leader byte repeated four times at the start of a KD data packet.
+pub const KD_DATA_LEADER_BYTE: u8 = 0x30;
+/// Leader byte repeated four times at the start of a KD control packet.
+pub const KD_CONTROL_LEADER_BYTE: u8 = 0x69;
+/// Byte terminating a KD data packet.
+pub const KD_PACKET_TRAILER: u8 = 0xAA;
+/// Length of the KD packet header, including the leader.
+pub const KD_PACKET_HEADER_LEN: usize = 16;
+/// Largest `byte_count` the framer accepts; larger counts are treated as line noise.
+pub const KD_PACKET_MAX_DATA_LEN: usize = 4000;
+
+/// Bytes the guest transmitted on the bridged port, stamped with the guest time (platform clock,
+/// in nanoseconds) at which the machine drained them from the UART.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KdChunk {
+    pub time_ns: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdFrameKind {
+    /// A complete data packet (leader `0x30303030`, including the trailing `0xAA`).
+    Data,
+    /// A complete control packet (leader `0x69696969`).
+    Control,
+    /// Bytes outside any packet (e.g. boot-time text or a malformed packet).
+    Unframed,
+}
+
+/// A delimited span of the guest byte stream; `time_ns` is the timestamp of its last byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KdFrame {
+    pub time_ns: u64,
+    pub kind: KdFrameKind,
+    pub bytes: Vec<u8>,
+}
+
+/// Incremental KD packet delimiter.
+///
+/// Feed it the guest byte stream with [`KdPacketFramer::push`]; complete packets (and the unframed
+/// bytes between them) are returned by [`KdPacketFramer::take_frames`]. Unframed bytes are emitted
+/// at the end of each push, so a run of them may be split across several frames.
+///
+/// A packet is recognised by four consecutive leader bytes. A header announcing more than
+/// [`KD_PACKET_MAX_DATA_LEN`] bytes, or a data packet without the trailer, is demoted to unframed
+/// bytes and scanning resumes after its leader.
+#[derive(Debug, Default, Clone)]
+pub struct KdPacketFramer {
+    /// Bytes of the frame currently being assembled (unframed run or partial packet).
+    pending: Vec<u8>,
+    /// Leader byte of the packet being assembled, once four leader bytes were seen.
+    packet: Option<u8>,
+    /// Length of the leader-byte run at the end of `pending` while scanning unframed bytes.
+    leader_run: usize,
+    frames: VecDeque<KdFrame>,
+}
+
+impl KdPacketFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, time_ns: u64, bytes: &[u8]) {
+        for &byte in bytes {
+            self.push_byte(time_ns, byte);
+        }
+        // Hand out unframed bytes promptly, keeping only a possible partial leader.
+        if self.packet.is_none() && self.pending.len() > self.leader_run {
+            let end = self.pending.len() - self.leader_run;
+            let unframed: Vec<u8> = self.pending.drain(..end).collect();
+            self.emit(time_ns, KdFrameKind::Unframed, unframed);
+        }
+    }
+
+    /// Drain the completed frames, in stream order.
+    pub fn take_frames(&mut self) -> Vec<KdFrame> {
+        self.frames.drain(..).collect()
+    }
+
+    /// Pop the oldest completed frame.
+    pub fn next_frame(&mut self) -> Option<KdFrame> {
+        self.frames.pop_front()
+    }
+
+    fn emit(&mut self, time_ns: u64, kind: KdFrameKind, bytes: Vec<u8>) {
+        self.frames.push_back(KdFrame {
+            time_ns,
+            kind,
+            bytes,
+        });
+    }
+
+    fn push_byte(&mut self, time_ns: u64, byte: u8) {
+        let Some(leader) = self.packet else {
+            self.scan_leader(time_ns, byte);
+            return;
+        };
+
+        self.pending.push(byte);
+        if self.pending.len() < KD_PACKET_HEADER_LEN {
+            return;
+        }
+        let data_len = usize::from(u16::from_le_bytes([self.pending[6], self.pending[7]]));
+        if data_len > KD_PACKET_MAX_DATA_LEN {
+            self.resync(time_ns);
+            return;
+        }
+        let trailer_len = usize::from(leader == KD_DATA_LEADER_BYTE);
+        let total = KD_PACKET_HEADER_LEN + data_len + trailer_len;
+        if self.pending.len() < total {
+            return;
+        }
+        if trailer_len == 1 && byte != KD_PACKET_TRAILER {
+            self.resync(time_ns);
+            return;
+        }
+
+        self.packet = None;
+        let kind = if leader == KD_DATA_LEADER_BYTE {
+            KdFrameKind::Data
+        } else {
+            KdFrameKind::Control
+        };
+        let bytes = std::mem::take(&mut self.pending);
+        self.emit(time_ns, kind, bytes);
+    }
+
+    fn scan_leader(&mut self, time_ns: u64, byte: u8) {
+        let is_leader = byte == KD_DATA_LEADER_BYTE || byte == KD_CONTROL_LEADER_BYTE;
+        if is_leader && self.leader_run > 0 && self.pending.last() == Some(&byte) {
+            self.leader_run += 1;
+        } else {
+            self.leader_run = usize::from(is_leader);
+        }
+        self.pending.push(byte);
+        if self.leader_run < 4 {
+            return;
+        }
+
+        // Split off the unframed bytes preceding the leader.
+        let leader_start = self.pending.len() - 4;
+        if leader_start > 0 {
+            let unframed: Vec<u8> = self.pending.drain(..leader_start).collect();
+            self.emit(time_ns, KdFrameKind::Unframed, unframed);
+        }
+        self.leader_run = 0;
+        self.packet = Some(byte);
+    }
+
+    /// Abandon the current packet: its leader becomes unframed and the rest is rescanned.
+    fn resync(&mut self, time_ns: u64) {
+        self.packet = None;
+        self.leader_run = 0;
+        let rest = self.pending.split_off(4);
+        for byte in rest {
+            self.push_byte(time_ns, byte);
+        }
+    }
+}
+
+/// Machine-side state of an enabled KD bridge.
+#[derive(Debug)]
+pub(crate) struct KdBridge {
+    pub(crate) port: usize,
+    /// Raw guest output, used when framing is disabled.
+    pub(crate) from_guest: VecDeque<KdChunk>,
+    /// Packet framer, used instead of `from_guest` when framing is enabled.
+    pub(crate) framer: Option<KdPacketFramer>,
+}
+
+impl KdBridge {
+    pub(crate) fn new(port: usize) -> Self {
+        Self {
+            port,
+            from_guest: VecDeque::new(),
+            framer: None,
+        }
+    }
+
+    pub(crate) fn push_from_guest(&mut self, time_ns: u64, bytes: Vec<u8>) {
+        match &mut self.framer {
+            Some(framer) => framer.push(time_ns, &bytes),
+            None => self.from_guest.push_back(KdChunk { time_ns, bytes }),
+        }
+    }
+}
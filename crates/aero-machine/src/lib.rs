@@ -24,23 +24,134 @@
 
 mod aerogpu;
 mod aerogpu_legacy_text;
+mod aerogpu_validation;
+mod bench;
+mod boot_stage;
+mod code_diagnostics;
+mod config_builder;
+mod device_snapshot;
+mod direct_boot;
+mod dirty_sampling;
+mod display_recording;
+mod event_injection;
+mod guest_ram;
 mod guest_time;
+mod input_latency;
+mod kd_bridge;
+mod machine_events;
+#[cfg(not(target_arch = "wasm32"))]
+mod machine_pair;
+mod nvram;
+mod paravirt;
+mod pci_info;
+mod perf;
+mod pointer_coalesce;
+mod post_code;
+mod reset_source;
+mod resource_map;
+mod run_budget;
+mod serial_ports;
 mod shared_disk;
 mod shared_iso_disk;
+mod snapshot_probe;
+mod text_screen;
+mod tick_scheduler;
+mod timer_coherence;
+mod unimplemented_report;
+mod unknown_access;
 mod vcpu_init;
+mod virt_debug;
+mod virtio_9p;
 pub mod virtual_time;
+mod watchdog;
 
 pub use aero_devices_gpu::{
     AeroGpuBackendCompletion, AeroGpuBackendSubmission, AeroGpuCommandBackend,
-    ImmediateAeroGpuBackend, NullAeroGpuBackend,
+    ImmediateAeroGpuBackend, NullAeroGpuBackend, SoftwareAeroGpuBackend,
+};
+use bench::BenchDevice;
+pub use bench::{
+    BenchOp, BenchOpStats, BenchResults, AERO_BENCH_PORT_BASE, AERO_BENCH_PORT_COUNT,
+    AERO_BENCH_SCRATCH_BYTES, AERO_BENCH_SIGNATURE,
+};
+pub use boot_stage::{BootStage, BootStageTransition};
+pub use code_diagnostics::{
+    RomWrite, RomWriteLog, SmcLog, SmcOccurrence, ROM_WRITE_LOG_CAP, SMC_LOG_CAP, SMC_MAX_RIPS,
+};
+pub use config_builder::{
+    ConfigDelta, GpuKind, MachineConfigBuilder, NicKind, StorageTopology, UsbControllers,
+    VirtioInputKind,
 };
+pub use device_snapshot::DeviceSnapshotInfo;
+pub use direct_boot::{
+    DirectBootError, DirectBootMode, DirectBootPaging, DirectBootSpec, DIRECT_BOOT_CODE32_SELECTOR,
+    DIRECT_BOOT_CODE64_SELECTOR, DIRECT_BOOT_DATA_SELECTOR,
+};
+pub use dirty_sampling::{DirtyRateStats, DIRTY_SAMPLING_HISTORY};
+pub use display_recording::{DisplayRecordingConfig, RecordedFrame, RecordedFrames};
+pub use event_injection::{InjectEventError, MAX_INJECTED_EVENTS};
+pub use guest_ram::RamRegionDescriptor;
 pub use guest_time::{GuestTime, DEFAULT_GUEST_CPU_HZ};
+pub use input_latency::{InputBackend, InputLatencySample, INPUT_LATENCY_HISTORY};
+pub use kd_bridge::{
+    KdChunk, KdFrame, KdFrameKind, KdPacketFramer, KD_CONTROL_LEADER_BYTE, KD_DATA_LEADER_BYTE,
+    KD_PACKET_HEADER_LEN, KD_PACKET_MAX_DATA_LEN, KD_PACKET_TRAILER,
+};
+pub use machine_events::{
+    MachineEvent, MachineEventCategory, MachineEventDrops, MachineEventKind, MachineEventMask,
+    MACHINE_EVENT_DEFAULT_CAPACITY, MACHINE_EVENT_MAX_CHUNK,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use machine_pair::{
+    LoopbackDirectionStats, LoopbackEndpoint, MachinePair, PairSide, DEFAULT_LOOPBACK_QUEUE_FRAMES,
+};
+pub use paravirt::{
+    feature_bits as paravirt_feature_bits, AeroFeatureId, ParavirtInfo, AERO_CPUID_BASE_LEAF,
+    AERO_CPUID_SIGNATURE, AERO_INPUT_BATCH_PROTOCOL_VERSION, AERO_MACHINE_ABI_VERSION,
+    AERO_MSR_BASE,
+};
+pub use pci_info::{PciBarInfo, PciDeviceInfo};
+pub use perf::{AccessCounts, DeviceTickCounts, MachinePerfCounters, MmioPerfCounts};
+pub use pointer_coalesce::{PointerCoalescing, PointerEventCounters, PointerQueueFullPolicy};
+pub use post_code::{PostCodeRecord, POST_CODE_HISTORY_CAPACITY};
+pub use reset_source::{ExceptionRecord, ResetSource};
+pub use resource_map::{PciIntxRoute, Resource, ResourceClaim, ResourceMap, ResourceMismatch};
+pub use run_budget::{RUN_BUDGET_MAX_BATCH_INSTS, RUN_BUDGET_MIN_BATCH_INSTS};
+pub use serial_ports::{
+    SerialChannel, SerialSink, SerialSinkId, SERIAL_PORT_BASES, SERIAL_PORT_COUNT,
+    SERIAL_PORT_DEFAULT_IRQS,
+};
 pub use shared_disk::SharedDisk;
 pub use shared_iso_disk::SharedIsoDisk;
 use shared_iso_disk::SharedIsoDiskWeak;
+pub use snapshot_probe::{
+    SnapshotProbe, SnapshotProbeRun, StateDivergence, StateFingerprint, FINGERPRINT_PAGE_SIZE,
+};
+pub use text_screen::{cp437_to_char, TextCell, TextScreen};
+use tick_scheduler::{TickDevice, TickScheduler};
+pub use timer_coherence::{
+    TimerCoherenceConfig, TimerCoherenceReport, TimerSource, TimerSourceSample,
+    DEFAULT_TIMER_COHERENCE_STEP_NS, DEFAULT_TIMER_COHERENCE_TOLERANCE_PPM,
+};
+pub use unimplemented_report::{
+    RefusedMsrAccess, UnimplementedInstruction, UnimplementedReport, ZeroCpuidLeaf,
+};
+pub use unknown_access::{
+    UnknownAccess, UnknownAccessConfig, UnknownAccessLog, UnknownAccessPolicy, UnknownAccessSpace,
+    DEFAULT_PORT_IO_IGNORE, UNKNOWN_ACCESS_LOG_CAP,
+};
+pub use virt_debug::{PageTableLevel, VirtTranslateError};
+pub use virtio_9p::{
+    SharedDirEntry, SharedFileStat, SharedFolderBackend, SharedFolderError, SharedOpenMode,
+    Virtio9p, DEFAULT_VIRTIO_9P_MOUNT_TAG,
+};
+pub use watchdog::{
+    HangDetails, HangVcpuState, HANG_WATCHDOG_RECENT_VECTORS, HANG_WATCHDOG_RIP_WINDOW,
+};
 
+use std::any::Any;
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::io::{self, Cursor, Read, Seek, Write};
 use std::rc::Rc;
@@ -53,15 +164,17 @@ use aero_cpu_core::assist::AssistContext;
 use aero_cpu_core::interp::tier0::exec::{run_batch_cpu_core_with_assists, BatchExit};
 use aero_cpu_core::interp::tier0::Tier0Config;
 use aero_cpu_core::interrupts::CpuExit;
-use aero_cpu_core::state::{gpr, CpuMode, CpuState, RFLAGS_IF};
+use aero_cpu_core::state::{gpr, CpuMode, CpuState, RFLAGS_CF, RFLAGS_IF};
 use aero_cpu_core::{AssistReason, CpuCore, Exception};
 use aero_devices::a20_gate::{A20Gate as A20GateDevice, A20_GATE_PORT};
 use aero_devices::acpi_pm::{
-    register_acpi_pm, AcpiPmCallbacks, AcpiPmConfig, AcpiPmIo, SharedAcpiPmIo,
+    register_acpi_pm, AcpiPmCallbacks, AcpiPmConfig, AcpiPmIo, AcpiSleepState, SharedAcpiPmIo,
+    PM1_STS_RTC,
 };
+pub use aero_devices::clock::TimerCatchupPolicy;
 use aero_devices::clock::{Clock, ManualClock};
 use aero_devices::debugcon::{register_debugcon, SharedDebugConLog};
-use aero_devices::dma::{register_dma8237, Dma8237};
+use aero_devices::dma::{register_dma8237, Dma8237, DmaClientError, SharedDmaChannelClient};
 use aero_devices::hpet;
 use aero_devices::i8042::{I8042Ports, SharedI8042Controller};
 use aero_devices::irq::{IrqLine, PlatformIrqLine};
@@ -73,10 +186,14 @@ use aero_devices::pci::{
     PciResourceAllocatorConfig, SharedPciConfigPorts,
 };
 use aero_devices::pic8259::register_pic8259_on_platform_interrupts;
+pub use aero_devices::pit8254::SpeakerEvent;
 use aero_devices::pit8254::{register_pit8254, Pit8254, SharedPit8254};
 use aero_devices::reset_ctrl::{ResetCtrl, RESET_CTRL_PORT};
-use aero_devices::rtc_cmos::{register_rtc_cmos, RtcCmos, SharedRtcCmos};
+use aero_devices::rtc_cmos::{
+    register_rtc_cmos, RtcCmos, SharedRtcCmos, CMOS_FLOPPY_TYPE_1_44M, CMOS_NVRAM_LEN,
+};
 use aero_devices::serial::{register_serial16550, Serial16550, SharedSerial16550};
+use aero_devices::tpm::{self, NullTpmBackend, TpmBackend, TpmCrb};
 use aero_devices::usb::ehci::EhciPciDevice;
 use aero_devices::usb::uhci::UhciPciDevice;
 use aero_devices::usb::xhci::XhciPciDevice;
@@ -84,13 +201,20 @@ pub use aero_devices_input::Ps2MouseButton;
 use aero_devices_nvme::{NvmeController, NvmePciDevice};
 use aero_devices_storage::ata::AtaDrive;
 use aero_devices_storage::atapi::{AtapiCdrom, IsoBackend};
+use aero_devices_storage::fdc::{
+    register_floppy_controller, FloppyController, SharedFloppyController, FDC_IRQ,
+};
 use aero_devices_storage::pci_ahci::AhciPciDevice;
 use aero_devices_storage::pci_ide::{Piix3IdePciDevice, PRIMARY_PORTS, SECONDARY_PORTS};
 use aero_gpu_vga::{
-    DisplayOutput as _, PortIO as _, VgaConfig, VgaDevice, VgaLegacyMmioHandler, VgaLfbMmioHandler,
-    VgaPortIoDevice,
+    DisplayOutput as _, PortIO as _, VgaConfig, VgaDac, VgaDevice, VgaLegacyMmioHandler,
+    VgaLfbMmioHandler, VgaPortIoDevice,
+};
+use aero_interrupts::apic::{
+    IOAPIC_MMIO_BASE, IOAPIC_MMIO_SIZE, LAPIC_MMIO_BASE, LAPIC_MMIO_SIZE, SECOND_IOAPIC_GSI_BASE,
+    SECOND_IOAPIC_MMIO_BASE,
 };
-use aero_interrupts::apic::{IOAPIC_MMIO_BASE, IOAPIC_MMIO_SIZE, LAPIC_MMIO_BASE, LAPIC_MMIO_SIZE};
+use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
 use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotReader as IoSnapshotReader, SnapshotResult as IoSnapshotResult,
     SnapshotVersion, SnapshotWriter,
@@ -98,7 +222,7 @@ use aero_io_snapshot::io::state::{
 use aero_io_snapshot::io::storage::dskc::DiskControllersSnapshot;
 use aero_net_backend::{FrameRing, L2TunnelRingBackend, L2TunnelRingBackendStats, NetworkBackend};
 use aero_net_e1000::E1000Device;
-use aero_net_pump::{tick_e1000, tick_virtio_net, VirtioNetBackendAdapter};
+use aero_net_pump::{tick_e1000_with_counts, tick_virtio_net, VirtioNetBackendAdapter};
 use aero_pc_constants::{PCI_MMIO_BASE, PCI_MMIO_SIZE};
 use aero_pc_platform::{PciIoBarHandler, PciIoBarRouter};
 use aero_platform::address_filter::AddressFilter;
@@ -109,38 +233,59 @@ use aero_platform::interrupts::{
     PlatformInterrupts,
 };
 use aero_platform::io::{IoPortBus, PortIoDevice as _};
-use aero_platform::memory::MemoryBus as PlatformMemoryBus;
+use aero_platform::memory::{BusMasterMemory, MemoryBus as PlatformMemoryBus};
 use aero_platform::reset::{ResetKind, ResetLatch};
 #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
 use aero_shared::cursor_state::{CursorState, CursorStateUpdate, CURSOR_FORMAT_B8G8R8A8};
 #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
 use aero_shared::scanout_state::{
-    ScanoutState, ScanoutStateUpdate, SCANOUT_FORMAT_B5G6R5, SCANOUT_FORMAT_B8G8R8X8,
-    SCANOUT_SOURCE_LEGACY_TEXT, SCANOUT_SOURCE_LEGACY_VBE_LFB, SCANOUT_SOURCE_WDDM,
+    ScanoutFrameSlots, ScanoutState, ScanoutStateUpdate, SCANOUT_FORMAT_B5G6R5,
+    SCANOUT_FORMAT_B8G8R8X8, SCANOUT_SOURCE_LEGACY_TEXT, SCANOUT_SOURCE_LEGACY_VBE_LFB,
+    SCANOUT_SOURCE_WDDM,
 };
 use aero_snapshot as snapshot;
 use aero_storage::{MemBackend, RawDisk};
+pub use aero_storage::{
+    StorageFaultCounters, StorageFaultDelay, StorageFaultError, StorageFaultInjector,
+    StorageFaultPolicy,
+};
 use aero_usb::hid::{
     GamepadReport, UsbHidConsumerControlHandle, UsbHidGamepadHandle, UsbHidKeyboardHandle,
     UsbHidMouseHandle,
 };
 use aero_usb::hub::UsbHubDevice;
-use aero_usb::usb2_port::Usb2PortMux;
-use aero_virtio::devices::blk::VirtioBlk;
+use aero_usb::passthrough::UsbHostAction;
+use aero_usb::usb2_port::{Usb2PortMux, Usb2PortOwner};
+use aero_usb::xhci::XhciController;
+use aero_usb::{ProxyUsbDescriptors, ProxyUsbDevice, ProxyUsbResult};
+use aero_virtio::devices::balloon::{VirtioBalloon, VirtioBalloonEvent, VirtioBalloonStats};
+pub use aero_virtio::devices::blk::VIRTIO_BLK_MAX_QUEUES;
+use aero_virtio::devices::blk::{VirtioBlk, VirtioBlkOptions};
 use aero_virtio::devices::input::{VirtioInput, VirtioInputDeviceKind};
 use aero_virtio::devices::net::VirtioNet;
+pub use aero_virtio::devices::rng::EntropySource;
+use aero_virtio::devices::rng::VirtioRng;
 use aero_virtio::memory::{
     GuestMemory as VirtioGuestMemory, GuestMemoryError as VirtioGuestMemoryError,
 };
 use aero_virtio::pci::{InterruptSink as VirtioInterruptSink, VirtioPciDevice};
 use firmware::bda::BiosDataArea;
-use firmware::bios::{A20Gate, Bios, BiosBus, BiosConfig, FirmwareMemory};
+use firmware::bios::{
+    A20Gate, Bios, BiosBus, BiosConfig, EddDevicePath, EddInterface, FirmwareMemory,
+};
+pub use firmware::bios::{BiosPostPhase, MemoryRegion, MemoryRegionKind, NumaNodeConfig};
 use memory::{
     DenseMemory, DirtyGuestMemory, DirtyTracker, GuestMemoryError, MapError, MemoryBus as _,
     MmioHandler, SparseMemory,
 };
+use reset_source::{PendingResetSource, SourcedResetSink};
 
-pub use crate::aerogpu::AeroGpuMmioDevice;
+pub use crate::aerogpu::{
+    AeroGpuMmioDevice, AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ, AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ,
+};
+pub use crate::aerogpu_validation::{
+    AeroGpuValidationFailure, AEROGPU_VALIDATION_CAPTURE_BYTES, AEROGPU_VALIDATION_FAILURE_CAP,
+};
 
 mod pci_firmware;
 use pci_firmware::SharedPciConfigPortsBiosAdapter;
@@ -193,6 +338,41 @@ impl memory::MemoryBus for AeroGpuBar1VramReadbackBus<'_> {
     }
 }
 
+/// Host-side view of the AeroGPU BAR1 VRAM backing store, returned by
+/// [`Machine::aerogpu_vram_export`].
+///
+/// On native targets `ptr` is a process pointer. On wasm32 the VRAM `Vec` lives in the module's
+/// linear memory, so `ptr as u32` is the byte offset of VRAM in that memory (shared with workers in
+/// `wasm-threaded` builds); see [`VramExportDescriptor::linear_memory_offset`].
+///
+/// # Safety contract
+///
+/// The pointer is only valid while the [`Machine`] that produced it is alive and its
+/// [`Machine::aerogpu_vram_generation`] still equals `generation`: a cold reset, snapshot restore
+/// or reconfiguration without AeroGPU clears, replaces or frees the backing and bumps the
+/// generation. Accesses must not race with the machine running on another thread; the guest and
+/// the host writing the same bytes concurrently is a data race.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VramExportDescriptor {
+    /// Start of the VRAM backing (byte offset `0` of BAR1).
+    pub ptr: *mut u8,
+    /// Length of the backing in bytes. This can be smaller than the guest-visible BAR1 aperture
+    /// when the full VRAM allocation failed; bytes past `len` read as zero through BAR1.
+    pub len: usize,
+    /// Guest-physical BAR1 base, if firmware or the guest has assigned one.
+    pub bar1_base: Option<u64>,
+    /// VRAM generation at the time of export.
+    pub generation: u64,
+}
+
+impl VramExportDescriptor {
+    /// Byte offset of VRAM within the wasm module's linear memory.
+    #[cfg(target_arch = "wasm32")]
+    pub fn linear_memory_offset(&self) -> u32 {
+        self.ptr as u32
+    }
+}
+
 const SNAPSHOT_DIRTY_PAGE_SIZE: u32 = 4096;
 const DEFAULT_E1000_MAC_ADDR: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
 const DEFAULT_VIRTIO_NET_MAC_ADDR: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x57];
@@ -201,6 +381,11 @@ const IA32_APIC_BASE_BSP_BIT: u64 = 1 << 8;
 // Use a sparse RAM backend once memory sizes exceed this threshold to avoid accidentally
 // allocating multi-GB buffers in tests and constrained environments.
 const SPARSE_RAM_THRESHOLD_BYTES: u64 = 512 * 1024 * 1024;
+// ISA IRQ the ACPI PM block signals SCI on (the FADT `SCI_INT` firmware publishes).
+const ACPI_SCI_IRQ: u8 = 9;
+// HID usages dropped by `Machine::inject_input_batch` while the keyboard is not grabbed: Left/Right
+// GUI (Windows) keys, which browsers and host desktops reserve for their own shortcuts.
+const DEFAULT_KEYBOARD_GRAB_FILTER: [u8; 2] = [0xE3, 0xE7];
 
 fn sync_msi_capability_into_config(
     cfg: &mut aero_devices::pci::PciConfigSpace,
@@ -271,7 +456,7 @@ fn sync_aerogpu_pci_state_into_mmio(
     dev: &mut AeroGpuMmioDevice,
 ) -> u16 {
     let bdf = aero_devices::pci::profile::AEROGPU.bdf;
-    let (command, bar0_base, bar1_base) = {
+    let (command, bar0_base, bar1_base, msix_state) = {
         let mut pci_cfg = pci_cfg.borrow_mut();
         let cfg = pci_cfg.bus_mut().device_config(bdf);
         let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
@@ -283,7 +468,10 @@ fn sync_aerogpu_pci_state_into_mmio(
             .and_then(|cfg| cfg.bar_range(aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX))
             .map(|range| range.base)
             .unwrap_or(0);
-        (command, bar0_base, bar1_base)
+        let msix_state = cfg
+            .and_then(|cfg| cfg.capability::<MsixCapability>())
+            .map(|msix| (msix.enabled(), msix.function_masked()));
+        (command, bar0_base, bar1_base, msix_state)
     };
 
     // Keep the AeroGPU model's internal PCI config image coherent with the canonical PCI config
@@ -295,6 +483,11 @@ fn sync_aerogpu_pci_state_into_mmio(
         aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
         bar1_base,
     );
+    if let Some((enabled, function_masked)) = msix_state {
+        sync_msix_capability_into_config(cfg, enabled, function_masked);
+    }
+    // Clearing the function mask makes vectors latched in the PBA deliverable.
+    dev.deliver_pending_msix();
     command
 }
 
@@ -313,6 +506,7 @@ pub use pc::{PcMachine, PcMachineConfig};
 trait LegacyVgaFrontend: aero_gpu_vga::PortIO {
     fn set_text_mode_80x25(&mut self);
     fn set_mode_13h(&mut self);
+    fn dac_mut(&mut self) -> &mut VgaDac;
 }
 
 impl LegacyVgaFrontend for VgaDevice {
@@ -323,6 +517,10 @@ impl LegacyVgaFrontend for VgaDevice {
     fn set_mode_13h(&mut self) {
         VgaDevice::set_mode_13h(self);
     }
+
+    fn dac_mut(&mut self) -> &mut VgaDac {
+        VgaDevice::dac_mut(self)
+    }
 }
 
 impl aero_gpu_vga::PortIO for AeroGpuDevice {
@@ -425,6 +623,10 @@ impl LegacyVgaFrontend for AeroGpuDevice {
         self.crtc_regs[0x0E] = 0x00;
         self.crtc_regs[0x0F] = 0x00;
     }
+
+    fn dac_mut(&mut self) -> &mut VgaDac {
+        &mut self.dac
+    }
 }
 
 /// Canonical BIOS boot device selection.
@@ -447,6 +649,59 @@ pub enum BootDevice {
     Cdrom,
 }
 
+/// Which virtio-pci interfaces a virtio device exposes.
+///
+/// See `docs/16-virtio-pci-legacy-transitional.md`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VirtioPciTransport {
+    /// Virtio 1.0+ capabilities only, with the modern `0x1040 + type` PCI device ID (the Windows 7
+    /// virtio driver contract).
+    #[default]
+    Modern,
+    /// Modern capabilities plus the virtio 0.9 register block in I/O BAR2, with the transitional
+    /// `0x1000 + type - 1` PCI device ID, so drivers that only speak the legacy interface can bind.
+    Transitional,
+}
+
+impl VirtioPciTransport {
+    fn pci_profile(
+        self,
+        profile: aero_devices::pci::profile::PciDeviceProfile,
+    ) -> aero_devices::pci::profile::PciDeviceProfile {
+        match self {
+            Self::Modern => profile,
+            Self::Transitional => aero_devices::pci::profile::virtio_transitional_profile(profile),
+        }
+    }
+
+    fn new_device(
+        self,
+        device: Box<dyn aero_virtio::devices::VirtioDevice>,
+        interrupts: Box<dyn VirtioInterruptSink>,
+    ) -> VirtioPciDevice {
+        match self {
+            Self::Modern => VirtioPciDevice::new(device, interrupts),
+            Self::Transitional => VirtioPciDevice::new_transitional(device, interrupts),
+        }
+    }
+}
+
+/// Memory balloon status returned by [`Machine::balloon_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalloonStats {
+    /// Host-requested balloon size in bytes (see [`Machine::balloon_set_target`]).
+    pub target_bytes: u64,
+    /// Balloon size the guest driver reports having reached, in bytes.
+    pub actual_bytes: u64,
+    /// Bytes of guest RAM currently committed by the host-side RAM backend.
+    ///
+    /// For sparse backends this shrinks as ballooned pages are released; dense backends always
+    /// report the full RAM size.
+    pub resident_ram_bytes: u64,
+    /// Most recent memory statistics reported by the guest driver, if any.
+    pub guest: Option<VirtioBalloonStats>,
+}
+
 /// AeroGPU submission payload drained from the guest-visible AeroGPU ring.
 ///
 /// This is an integration hook for browser/WASM builds where the guest-visible AeroGPU PCI device
@@ -493,6 +748,16 @@ pub struct MachineConfig {
     ///
     /// See `docs/21-smp.md` for the current SMP status and roadmap.
     pub cpu_count: u8,
+    /// Optional NUMA topology published to the guest via ACPI `SRAT`/`SLIT`.
+    ///
+    /// Every vCPU in `0..cpu_count` must belong to exactly one node, node memory sizes must be
+    /// non-zero multiples of 1MiB and sum to [`MachineConfig::ram_size_bytes`], and ACPI must be
+    /// enabled. Nodes take consecutive slices of guest RAM in order; all nodes are backed by the
+    /// same host memory, so this is purely a guest-visible topology for scheduler and allocator
+    /// experiments.
+    ///
+    /// Forwarded to [`firmware::bios::BiosConfig::numa_nodes`].
+    pub numa_nodes: Option<Vec<NumaNodeConfig>>,
     /// Preferred BIOS boot device (HDD vs CD-ROM).
     ///
     /// This is a higher-level selector for choosing a raw BIOS drive number without the caller
@@ -508,6 +773,15 @@ pub struct MachineConfig {
     /// When using the provided [`Machine`] setters (`set_boot_device` / `set_boot_drive`), this is
     /// kept in sync with [`MachineConfig::boot_drive`].
     pub boot_device: BootDevice,
+    /// Optional multi-entry firmware boot order.
+    ///
+    /// When non-empty, BIOS POST tries each entry in turn (HDD boot sector, El Torito CD-ROM,
+    /// floppy) and boots the first bootable one. When empty (the default), the single-drive
+    /// [`MachineConfig::boot_drive`] policy applies.
+    ///
+    /// Forwarded to [`firmware::bios::BiosConfig::boot_order`]; see also
+    /// [`Machine::set_boot_order`].
+    pub boot_order: Vec<firmware::bios::BootEntry>,
     /// Deterministic seed used to generate the SMBIOS Type 1 "System UUID".
     ///
     /// Runtimes that need stable per-VM identities (e.g. Windows guests) should set this to a
@@ -515,6 +789,13 @@ pub struct MachineConfig {
     ///
     /// Forwarded to [`firmware::bios::BiosConfig::smbios_uuid_seed`].
     pub smbios_uuid_seed: u64,
+    /// SMBIOS system/baseboard/chassis identity strings and Type 11 OEM strings.
+    ///
+    /// Unset fields use the built-in Aero defaults.
+    ///
+    /// Forwarded to [`firmware::bios::BiosConfig::smbios`]; see also
+    /// [`Machine::set_smbios_identity`].
+    pub smbios: firmware::smbios::SmbiosIdentity,
     /// Whether to attach canonical PC platform devices (PIC/APIC/PIT/RTC/PCI/ACPI PM/HPET).
     ///
     /// This is currently opt-in to keep the default machine minimal and deterministic.
@@ -558,7 +839,20 @@ pub struct MachineConfig {
     ///
     /// Requires [`MachineConfig::enable_pc_platform`].
     pub enable_virtio_blk: bool,
-    /// Whether to attach virtio-input keyboard + mouse devices (virtio-pci modern transport).
+    /// Optional number of virtio-blk request queues (`1..=`[`VIRTIO_BLK_MAX_QUEUES`]).
+    ///
+    /// When set, the device leaves the Windows 7 contract profile: `VIRTIO_BLK_F_MQ` is offered
+    /// for more than one queue (each queue gets its own MSI-X vector), and `seg_max`/`size_max`
+    /// advertise the per-request limits the device actually enforces so drivers can submit up to
+    /// 4MiB per request through indirect descriptors. When unset, the device keeps the contract
+    /// v1 single-queue profile (`docs/windows7-virtio-driver-contract.md`).
+    ///
+    /// Note: This is only used when [`MachineConfig::enable_virtio_blk`] is set.
+    pub virtio_blk_num_queues: Option<u16>,
+    /// virtio-pci interfaces exposed by the virtio-blk controller.
+    pub virtio_blk_transport: VirtioPciTransport,
+    /// Whether to attach virtio-input keyboard + mouse devices (virtio-pci modern transport unless
+    /// [`MachineConfig::virtio_input_transport`] selects the transitional one).
     ///
     /// This exposes a multi-function PCI device with two functions at stable BDFs:
     /// - Keyboard: `aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD.bdf` (`00:0a.0`)
@@ -581,6 +875,47 @@ pub struct MachineConfig {
     /// Requires [`MachineConfig::enable_virtio_input`] (function 0 must exist and be marked as
     /// multi-function for OSes to enumerate additional functions).
     pub enable_virtio_input_tablet: bool,
+    /// virtio-pci interfaces exposed by every virtio-input function.
+    pub virtio_input_transport: VirtioPciTransport,
+    /// Whether to attach a virtio memory balloon at
+    /// `aero_devices::pci::profile::VIRTIO_BALLOON.bdf` (`00:0e.0`).
+    ///
+    /// Pages the guest driver hands to the balloon are discarded from the guest RAM backend, so
+    /// sparse backends release them; see [`Machine::balloon_set_target`] and
+    /// [`Machine::balloon_stats`].
+    ///
+    /// Requires [`MachineConfig::enable_pc_platform`].
+    pub enable_virtio_balloon: bool,
+    /// virtio-pci interfaces exposed by the virtio balloon.
+    pub virtio_balloon_transport: VirtioPciTransport,
+    /// Whether to attach a virtio-9p (9P2000.L) host folder share at
+    /// `aero_devices::pci::profile::VIRTIO_9P.bdf` (`00:0f.0`).
+    ///
+    /// The shared folder is provided by the host via [`Machine::set_shared_folder_backend`]; until
+    /// then the guest's attach requests fail. Windows has no inbox 9p client, so this is mainly
+    /// useful for Linux guests (`mount -t 9p -o trans=virtio,version=9p2000.L <tag> <dir>`).
+    ///
+    /// Requires [`MachineConfig::enable_pc_platform`].
+    pub enable_virtio_9p: bool,
+    /// Reject every guest request that would modify the shared folder (`EROFS`).
+    pub virtio_9p_read_only: bool,
+    /// Mount tag the guest uses to select the share (truncated to 32 bytes).
+    pub virtio_9p_mount_tag: String,
+    /// virtio-pci interfaces exposed by the virtio-9p device.
+    pub virtio_9p_transport: VirtioPciTransport,
+    /// Whether to attach a virtio-rng entropy device at
+    /// `aero_devices::pci::profile::VIRTIO_RNG.bdf` (`00:10.0`).
+    ///
+    /// Guest requests are filled from a deterministic ChaCha20 stream seeded from
+    /// [`MachineConfig::rng_seed`], unless the host installs its own source via
+    /// [`Machine::set_entropy_source`].
+    ///
+    /// Requires [`MachineConfig::enable_pc_platform`].
+    pub enable_virtio_rng: bool,
+    /// Seed of the deterministic virtio-rng entropy stream.
+    pub rng_seed: u64,
+    /// virtio-pci interfaces exposed by the virtio-rng device.
+    pub virtio_rng_transport: VirtioPciTransport,
     /// Whether to attach an Intel PIIX3 UHCI (USB 1.1) controller at the canonical BDF
     /// (`aero_devices::pci::profile::USB_UHCI_PIIX3.bdf`, `00:01.2`).
     ///
@@ -613,8 +948,17 @@ pub struct MachineConfig {
     /// - hub port 3: USB HID gamepad (Aero's fixed 8-byte report)
     /// - hub port 4: USB HID consumer-control (media keys, Usage Page 0x0C)
     ///
-    /// Requires [`MachineConfig::enable_uhci`].
+    /// Requires [`MachineConfig::enable_uhci`] (or [`MachineConfig::enable_xhci`] with
+    /// [`MachineConfig::synthetic_usb_hid_on_xhci`]).
     pub enable_synthetic_usb_hid: bool,
+    /// Attach the synthetic USB HID topology (see [`MachineConfig::enable_synthetic_usb_hid`]) to
+    /// xHCI root port 0 instead of UHCI, for guests that only ship an xHCI driver.
+    ///
+    /// The hub keeps the same downstream port numbering but exposes 15 ports (the most an xHCI
+    /// Route String can address).
+    ///
+    /// Requires [`MachineConfig::enable_xhci`].
+    pub synthetic_usb_hid_on_xhci: bool,
     /// Whether to attach the legacy VGA/VBE device model.
     ///
     /// This is the transitional standalone VGA/VBE path used for BIOS/boot display and VGA-focused
@@ -753,13 +1097,43 @@ pub struct MachineConfig {
     /// Requires [`MachineConfig::enable_pc_platform`] (PCI enumeration required) and is mutually
     /// exclusive with [`MachineConfig::enable_vga`].
     pub enable_aerogpu: bool,
+    /// Optional override for the AeroGPU VRAM size in bytes.
+    ///
+    /// This sets both the guest-visible BAR1 aperture size and the VRAM backing allocation, and
+    /// therefore also the VBE `TotalMemory` reported by the BIOS. Must be a power of two within
+    /// [`AEROGPU_VRAM_SIZE_MIN`]..=[`AEROGPU_VRAM_SIZE_MAX`].
+    ///
+    /// When unset, BAR1 uses the canonical profile size
+    /// ([`aero_devices::pci::profile::AEROGPU_VRAM_SIZE`]); wasm32 builds then back only a 32MiB
+    /// prefix of it to fit the runtime heap.
+    ///
+    /// Note: This is only used when [`MachineConfig::enable_aerogpu`] is set.
+    pub aerogpu_vram_size_bytes: Option<usize>,
     /// Whether to attach a COM1 16550 serial device at `0x3F8`.
     pub enable_serial: bool,
+    /// Whether to attach a COM2 16550 serial device at `0x2F8`.
+    ///
+    /// Windows kernel debugging (KDCOM) conventionally uses COM2; see [`Machine::serial_channel`].
+    pub enable_com2: bool,
+    /// Whether to attach a COM3 16550 serial device at `0x3E8`.
+    pub enable_com3: bool,
+    /// Whether to attach a COM4 16550 serial device at `0x2E8`.
+    pub enable_com4: bool,
+    /// ISA IRQ used by each of COM1..COM4 (default: [`SERIAL_PORT_DEFAULT_IRQS`]).
+    ///
+    /// Ports sharing an IRQ are wired-OR. Only entries for enabled ports are validated.
+    pub serial_irqs: [u8; SERIAL_PORT_COUNT],
     /// Whether to attach an ISA DebugCon logging port at `0xE9`.
     ///
     /// This is a Bochs/QEMU-compatible debug device used for simple early boot logging (e.g. before
     /// the guest initializes a serial console).
     pub enable_debugcon: bool,
+    /// Whether to attach the synthetic aero-bench microbenchmark device at
+    /// [`AERO_BENCH_PORT_BASE`] (see [`Machine::bench_results`]).
+    ///
+    /// The device is not described in ACPI or SMBIOS, so guests only see it when they probe for
+    /// it. Its registers and counters are not part of snapshots.
+    pub enable_aero_bench: bool,
     /// Whether to attach a legacy i8042 controller at ports `0x60/0x64`.
     pub enable_i8042: bool,
     /// Whether to attach a "fast A20" gate device at port `0x92`.
@@ -772,12 +1146,50 @@ pub struct MachineConfig {
     pub enable_e1000: bool,
     /// Optional MAC address for the E1000 NIC.
     pub e1000_mac_addr: Option<[u8; 6]>,
-    /// Whether to attach a virtio-net PCI NIC (virtio-pci **modern** transport).
+    /// Whether to attach a virtio-net PCI NIC (virtio-pci **modern** transport unless
+    /// [`MachineConfig::virtio_net_transport`] selects the transitional one).
     ///
     /// Requires [`MachineConfig::enable_pc_platform`].
     pub enable_virtio_net: bool,
     /// Optional MAC address for the virtio-net device.
     pub virtio_net_mac_addr: Option<[u8; 6]>,
+    /// virtio-pci interfaces exposed by the virtio-net device.
+    pub virtio_net_transport: VirtioPciTransport,
+    /// Whether to attach a TPM 2.0 device (CRB interface, locality 0) at `0xFED4_0000`.
+    ///
+    /// Command processing is delegated to a [`aero_devices::tpm::TpmBackend`] installed via
+    /// [`Machine::set_tpm_backend`]; until then a [`aero_devices::tpm::NullTpmBackend`] answers
+    /// `TPM2_Startup`/`TPM2_SelfTest` only. When ACPI tables are published, POST also emits a
+    /// `TPM2` table and a `MSFT0101` DSDT device so guests can discover it.
+    pub enable_tpm: bool,
+    /// Whether to add a second I/O APIC at `0xFEC0_1000` serving GSIs 24-47.
+    ///
+    /// The ACPI `MADT` then describes both controllers (IDs 0 and 1). Pins of the second I/O APIC
+    /// are wired active-low, matching the PCI INTx lines routed to them via
+    /// [`MachineConfig::pci_pirq_to_gsi`]. Requires [`MachineConfig::enable_pc_platform`] to have any effect.
+    pub enable_second_ioapic: bool,
+    /// GSI each PCI PIRQ[A-D] is routed to (default: GSIs 10-13, see [`PciIntxRouterConfig`]).
+    ///
+    /// GSIs below 16 power on as the PIIX3 PIRQ route registers and remain guest-reprogrammable.
+    /// GSIs 16 and up are hardwired I/O APIC inputs that the PIRQ route registers cannot express
+    /// (they read back as disabled); GSIs 24-47 require [`MachineConfig::enable_second_ioapic`].
+    /// ACPI `_PRT` and the PCI interrupt lines follow this table.
+    pub pci_pirq_to_gsi: [u32; 4],
+    /// Whether [`Machine::resync_wall_clock`] also raises the ACPI fixed RTC event
+    /// (`PM1_STS.RTC_STS`), notifying ACPI guests that armed `RTC_EN` of the time change.
+    pub acpi_rtc_resync_event: bool,
+    /// Non-volatile state saved by [`Machine::export_nvram`] in an earlier session (e.g. the
+    /// guest's CMOS settings), applied before the first POST.
+    ///
+    /// Firmware-owned fields (memory sizes, equipment/floppy bytes, CMOS checksum) are still
+    /// derived from this configuration and overwrite the imported values.
+    pub initial_nvram: Option<Vec<u8>>,
+    /// When host-injected relative pointer motion (virtio-input mouse and PS/2 mouse) is handed
+    /// to the guest device. Anything other than [`PointerCoalescing::Off`] sums pending motion and
+    /// wheel deltas into a single report; button transitions are never merged.
+    pub pointer_coalescing: PointerCoalescing,
+    /// What pointer injection does when the guest device's event queue is full.
+    pub pointer_queue_full_policy: PointerQueueFullPolicy,
 }
 
 impl Default for MachineConfig {
@@ -786,28 +1198,50 @@ impl Default for MachineConfig {
             ram_size_bytes: 64 * 1024 * 1024,
             boot_drive: 0x80,
             cpu_count: 1,
+            numa_nodes: None,
             boot_device: BootDevice::Hdd,
+            boot_order: Vec::new(),
             smbios_uuid_seed: 0,
+            smbios: firmware::smbios::SmbiosIdentity::default(),
             enable_pc_platform: false,
             enable_acpi: false,
             enable_ahci: false,
             enable_nvme: false,
             enable_ide: false,
             enable_virtio_blk: false,
+            virtio_blk_num_queues: None,
+            virtio_blk_transport: VirtioPciTransport::Modern,
             enable_virtio_input: false,
             enable_virtio_input_tablet: false,
+            virtio_input_transport: VirtioPciTransport::Modern,
+            enable_virtio_balloon: false,
+            virtio_balloon_transport: VirtioPciTransport::Modern,
+            enable_virtio_9p: false,
+            virtio_9p_read_only: false,
+            virtio_9p_mount_tag: DEFAULT_VIRTIO_9P_MOUNT_TAG.to_string(),
+            virtio_9p_transport: VirtioPciTransport::Modern,
+            enable_virtio_rng: false,
+            rng_seed: 0,
+            virtio_rng_transport: VirtioPciTransport::Modern,
             enable_uhci: false,
             enable_ehci: false,
             enable_xhci: false,
             enable_synthetic_usb_hid: false,
+            synthetic_usb_hid_on_xhci: false,
             enable_aerogpu: false,
+            aerogpu_vram_size_bytes: None,
             enable_vga: true,
             vga_lfb_base: None,
             vga_lfb_offset: None,
             vga_vram_bar_base: None,
             vga_vram_size_bytes: None,
             enable_serial: true,
+            enable_com2: false,
+            enable_com3: false,
+            enable_com4: false,
+            serial_irqs: SERIAL_PORT_DEFAULT_IRQS,
             enable_debugcon: true,
+            enable_aero_bench: false,
             enable_i8042: true,
             enable_a20_gate: true,
             enable_reset_ctrl: true,
@@ -815,6 +1249,14 @@ impl Default for MachineConfig {
             e1000_mac_addr: None,
             enable_virtio_net: false,
             virtio_net_mac_addr: None,
+            virtio_net_transport: VirtioPciTransport::Modern,
+            enable_tpm: false,
+            enable_second_ioapic: false,
+            pci_pirq_to_gsi: PciIntxRouterConfig::default().pirq_to_gsi,
+            acpi_rtc_resync_event: false,
+            initial_nvram: None,
+            pointer_coalescing: PointerCoalescing::Off,
+            pointer_queue_full_policy: PointerQueueFullPolicy::DropOldestMotion,
         }
     }
 }
@@ -844,28 +1286,50 @@ impl MachineConfig {
             ram_size_bytes,
             boot_drive: 0x80,
             cpu_count: 1,
+            numa_nodes: None,
             boot_device: BootDevice::Hdd,
+            boot_order: Vec::new(),
             smbios_uuid_seed: 0,
+            smbios: firmware::smbios::SmbiosIdentity::default(),
             enable_pc_platform: true,
             enable_acpi: true,
             enable_ahci: true,
             enable_nvme: false,
             enable_ide: true,
             enable_virtio_blk: false,
+            virtio_blk_num_queues: None,
+            virtio_blk_transport: VirtioPciTransport::Modern,
             enable_virtio_input: false,
             enable_virtio_input_tablet: false,
+            virtio_input_transport: VirtioPciTransport::Modern,
+            enable_virtio_balloon: false,
+            virtio_balloon_transport: VirtioPciTransport::Modern,
+            enable_virtio_9p: false,
+            virtio_9p_read_only: false,
+            virtio_9p_mount_tag: DEFAULT_VIRTIO_9P_MOUNT_TAG.to_string(),
+            virtio_9p_transport: VirtioPciTransport::Modern,
+            enable_virtio_rng: false,
+            rng_seed: 0,
+            virtio_rng_transport: VirtioPciTransport::Modern,
             enable_uhci: false,
             enable_ehci: false,
             enable_xhci: false,
             enable_synthetic_usb_hid: false,
+            synthetic_usb_hid_on_xhci: false,
             enable_aerogpu: false,
+            aerogpu_vram_size_bytes: None,
             enable_vga: true,
             vga_lfb_base: None,
             vga_lfb_offset: None,
             vga_vram_bar_base: None,
             vga_vram_size_bytes: None,
             enable_serial: true,
+            enable_com2: false,
+            enable_com3: false,
+            enable_com4: false,
+            serial_irqs: SERIAL_PORT_DEFAULT_IRQS,
             enable_debugcon: true,
+            enable_aero_bench: false,
             enable_i8042: true,
             enable_a20_gate: true,
             enable_reset_ctrl: true,
@@ -873,6 +1337,14 @@ impl MachineConfig {
             e1000_mac_addr: None,
             enable_virtio_net: false,
             virtio_net_mac_addr: None,
+            virtio_net_transport: VirtioPciTransport::Modern,
+            enable_tpm: false,
+            enable_second_ioapic: false,
+            pci_pirq_to_gsi: PciIntxRouterConfig::default().pirq_to_gsi,
+            acpi_rtc_resync_event: false,
+            initial_nvram: None,
+            pointer_coalescing: PointerCoalescing::Off,
+            pointer_queue_full_policy: PointerQueueFullPolicy::DropOldestMotion,
         }
     }
 
@@ -953,7 +1425,8 @@ impl MachineConfig {
     /// - AeroGPU enabled (`00:07.0`, `A3A0:0001`) for Windows driver binding
     ///
     /// See `docs/05-storage-topology-win7.md` for the normative storage BDFs and media attachment
-    /// mapping.
+    /// mapping. The exact set of fields this preset changes relative to [`MachineConfig::default`]
+    /// is pinned by a golden [`MachineConfig::diff`] test (`tests/machine_config_builder.rs`).
     #[must_use]
     pub fn browser_defaults(ram_size_bytes: u64) -> Self {
         let mut cfg = Self::win7_storage_defaults(ram_size_bytes);
@@ -990,18 +1463,52 @@ impl MachineConfig {
 /// A single-step/run invocation result.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RunExit {
-    /// The slice completed because `max_insts` was reached.
+    /// The slice completed because `max_insts` was reached (for [`Machine::run_for`] and
+    /// [`Machine::run_until_deadline`]: because the budget elapsed or the host check fired).
     Completed { executed: u64 },
     /// The CPU executed `HLT`.
     Halted { executed: u64 },
-    /// The guest requested a reset (e.g. via port `0xCF9`).
-    ResetRequested { kind: ResetKind, executed: u64 },
+    /// The guest requested a reset (e.g. via port `0xCF9`, or by triple faulting).
+    ///
+    /// The request stays pending until the host acts on it; see [`Machine::pending_reset`].
+    ResetRequested {
+        kind: ResetKind,
+        source: ResetSource,
+        executed: u64,
+    },
+    /// The guest entered ACPI soft-off (S5, or S4 after writing its hibernate image) via
+    /// `PM1a_CNT.SLP_TYP/SLP_EN`. Every further `run_slice` reports this exit without executing
+    /// until the machine is reset (see [`Machine::power_state`]).
+    PowerOff { executed: u64 },
     /// Execution stopped because the CPU core needs host assistance.
     Assist { reason: AssistReason, executed: u64 },
     /// Execution stopped due to an exception/fault.
     Exception { exception: Exception, executed: u64 },
-    /// Execution stopped due to a fatal CPU exit condition (e.g. triple fault).
+    /// Execution stopped due to a fatal CPU exit condition (e.g. a non-architectural memory
+    /// fault). [`Machine`] reports triple faults as [`RunExit::ResetRequested`].
     CpuExit { exit: CpuExit, executed: u64 },
+    /// The guest made no observable progress for the armed hang watchdog threshold (see
+    /// [`Machine::set_hang_watchdog`]). Execution can be resumed with another `run_slice` call.
+    HangSuspected { details: HangDetails, executed: u64 },
+    /// A host-injected SMI (see [`Machine::inject_smi_stub`]) was pending. System management mode
+    /// is not modeled, so the SMI is dropped; execution can be resumed with another `run_slice`.
+    SmiUnsupported { executed: u64 },
+    /// The guest accessed a port no device decodes while the port I/O policy is
+    /// [`UnknownAccessPolicy::Trap`] (see [`Machine::set_unknown_access_config`]).
+    UnknownPortIo {
+        port: u16,
+        size: u8,
+        write: bool,
+        executed: u64,
+    },
+    /// The guest accessed an unmapped physical address while the MMIO policy is
+    /// [`UnknownAccessPolicy::Trap`] (see [`Machine::set_unknown_access_config`]).
+    UnknownMmio {
+        paddr: u64,
+        size: u32,
+        write: bool,
+        executed: u64,
+    },
 }
 
 impl RunExit {
@@ -1011,19 +1518,60 @@ impl RunExit {
             RunExit::Completed { executed }
             | RunExit::Halted { executed }
             | RunExit::ResetRequested { executed, .. }
+            | RunExit::PowerOff { executed }
+            | RunExit::Assist { executed, .. }
+            | RunExit::Exception { executed, .. }
+            | RunExit::CpuExit { executed, .. }
+            | RunExit::HangSuspected { executed, .. }
+            | RunExit::SmiUnsupported { executed }
+            | RunExit::UnknownPortIo { executed, .. }
+            | RunExit::UnknownMmio { executed, .. } => executed,
+        }
+    }
+
+    /// Replace the executed instruction count (used to report totals across several slices).
+    fn with_executed(mut self, total: u64) -> Self {
+        match &mut self {
+            RunExit::Completed { executed }
+            | RunExit::Halted { executed }
+            | RunExit::ResetRequested { executed, .. }
+            | RunExit::PowerOff { executed }
             | RunExit::Assist { executed, .. }
             | RunExit::Exception { executed, .. }
-            | RunExit::CpuExit { executed, .. } => executed,
+            | RunExit::CpuExit { executed, .. }
+            | RunExit::HangSuspected { executed, .. }
+            | RunExit::SmiUnsupported { executed }
+            | RunExit::UnknownPortIo { executed, .. }
+            | RunExit::UnknownMmio { executed, .. } => *executed = total,
         }
+        self
     }
 }
 
+/// Guest power state as derived from the ACPI sleep requests the guest performs (see
+/// [`Machine::power_state`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuestPowerState {
+    /// The guest is running (the state after every reset).
+    #[default]
+    Running,
+    /// The guest entered a sleep state (S1..S3) and waits for a wake event (see
+    /// [`Machine::acpi_wake`]).
+    Sleeping,
+    /// The guest entered soft-off (S5, or S4 hibernate); [`Machine::run_slice`] reports
+    /// [`RunExit::PowerOff`] until the machine is reset.
+    SoftOff,
+}
+
 /// Errors returned when constructing or configuring a [`Machine`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MachineError {
     InvalidCpuCount(u8),
     InvalidDiskSize(usize),
     DiskBackend(String),
+    /// A disk operation failed. The [`aero_storage::DiskError`] is kept whole, including the
+    /// layer/offset context its wrappers attached.
+    Disk(aero_storage::DiskError),
     GuestMemoryTooLarge(u64),
     GuestMemorySizeMismatch {
         expected: u64,
@@ -1043,16 +1591,43 @@ pub enum MachineError {
     VirtioBlkRequiresPcPlatform,
     VirtioInputRequiresPcPlatform,
     VirtioInputTabletRequiresVirtioInput,
+    VirtioBalloonRequiresPcPlatform,
+    Virtio9pRequiresPcPlatform,
+    VirtioRngRequiresPcPlatform,
     UhciRequiresPcPlatform,
     SyntheticUsbHidRequiresUhci,
+    SyntheticUsbHidRequiresXhci,
     EhciRequiresPcPlatform,
     XhciRequiresPcPlatform,
     AeroGpuRequiresPcPlatform,
     AeroGpuConflictsWithVga,
     AeroGpuNotEnabled,
+    /// [`MachineConfig::aerogpu_vram_size_bytes`] is not a power of two within
+    /// [`AEROGPU_VRAM_SIZE_MIN`]..=[`AEROGPU_VRAM_SIZE_MAX`].
+    InvalidAeroGpuVramSize(usize),
+    /// [`MachineConfig::virtio_blk_num_queues`] is outside `1..=`[`VIRTIO_BLK_MAX_QUEUES`].
+    InvalidVirtioBlkQueueCount(u16),
     E1000RequiresPcPlatform,
     VirtioNetRequiresPcPlatform,
     MultipleNicsEnabled,
+    /// A COM port was routed to an ISA IRQ that cannot carry a serial interrupt.
+    InvalidSerialIrq {
+        port: usize,
+        irq: u8,
+    },
+    /// [`MachineConfig::numa_nodes`] does not describe a valid topology.
+    InvalidNumaTopology(String),
+    /// A PCI PIRQ was routed to a GSI the platform cannot deliver.
+    InvalidPciPirqGsi {
+        pirq: usize,
+        gsi: u32,
+    },
+    /// ISA DMA clients can only be registered when the PC platform (and its 8237) is enabled.
+    IsaDmaRequiresPcPlatform,
+    IsaDmaClient(DmaClientError),
+    /// An NVRAM blob ([`MachineConfig::initial_nvram`] / [`Machine::import_nvram`]) is not one
+    /// produced by [`Machine::export_nvram`].
+    InvalidNvram(aero_io_snapshot::io::state::SnapshotError),
 }
 
 impl fmt::Display for MachineError {
@@ -1070,6 +1645,7 @@ impl fmt::Display for MachineError {
                 aero_storage::SECTOR_SIZE
             ),
             MachineError::DiskBackend(msg) => write!(f, "disk backend error: {msg}"),
+            MachineError::Disk(err) => write!(f, "disk backend error: {err}"),
             MachineError::GuestMemoryTooLarge(size) => write!(
                 f,
                 "guest RAM size {size} bytes does not fit in the current platform's usize"
@@ -1112,6 +1688,12 @@ impl fmt::Display for MachineError {
             MachineError::VirtioBlkRequiresPcPlatform => {
                 write!(f, "enable_virtio_blk requires enable_pc_platform=true")
             }
+            MachineError::InvalidVirtioBlkQueueCount(count) => {
+                write!(
+                    f,
+                    "invalid virtio_blk_num_queues={count}; must be between 1 and {VIRTIO_BLK_MAX_QUEUES}"
+                )
+            }
             MachineError::VirtioInputRequiresPcPlatform => {
                 write!(f, "enable_virtio_input requires enable_pc_platform=true")
             }
@@ -1121,12 +1703,27 @@ impl fmt::Display for MachineError {
                     "enable_virtio_input_tablet requires enable_virtio_input=true"
                 )
             }
+            MachineError::VirtioBalloonRequiresPcPlatform => {
+                write!(f, "enable_virtio_balloon requires enable_pc_platform=true")
+            }
+            MachineError::Virtio9pRequiresPcPlatform => {
+                write!(f, "enable_virtio_9p requires enable_pc_platform=true")
+            }
+            MachineError::VirtioRngRequiresPcPlatform => {
+                write!(f, "enable_virtio_rng requires enable_pc_platform=true")
+            }
             MachineError::UhciRequiresPcPlatform => {
                 write!(f, "enable_uhci requires enable_pc_platform=true")
             }
             MachineError::SyntheticUsbHidRequiresUhci => {
                 write!(f, "enable_synthetic_usb_hid requires enable_uhci=true")
             }
+            MachineError::SyntheticUsbHidRequiresXhci => {
+                write!(
+                    f,
+                    "enable_synthetic_usb_hid with synthetic_usb_hid_on_xhci requires enable_xhci=true"
+                )
+            }
             MachineError::EhciRequiresPcPlatform => {
                 write!(f, "enable_ehci requires enable_pc_platform=true")
             }
@@ -1142,6 +1739,12 @@ impl fmt::Display for MachineError {
             MachineError::AeroGpuNotEnabled => {
                 write!(f, "aerogpu device is not enabled (enable_aerogpu=false)")
             }
+            MachineError::InvalidAeroGpuVramSize(size) => {
+                write!(
+                    f,
+                    "invalid aerogpu_vram_size_bytes={size:#x}; must be a power of two between {AEROGPU_VRAM_SIZE_MIN:#x} and {AEROGPU_VRAM_SIZE_MAX:#x}"
+                )
+            }
             MachineError::E1000RequiresPcPlatform => {
                 write!(f, "enable_e1000 requires enable_pc_platform=true")
             }
@@ -1152,21 +1755,57 @@ impl fmt::Display for MachineError {
                 f,
                 "cannot enable both enable_e1000 and enable_virtio_net (choose exactly one NIC)"
             ),
+            MachineError::InvalidSerialIrq { port, irq } => write!(
+                f,
+                "invalid serial_irqs[{port}]={irq}; COM ports must use ISA IRQ 1 or 3..=15"
+            ),
+            MachineError::InvalidNumaTopology(msg) => write!(f, "invalid numa_nodes: {msg}"),
+            MachineError::InvalidPciPirqGsi { pirq, gsi } => write!(
+                f,
+                "invalid pci_pirq_to_gsi[{pirq}]={gsi}; PCI INTx must use ISA IRQ 3..=7 or 9..=15, GSI 16..=23, or GSI 24..=47 with enable_second_ioapic=true"
+            ),
+            MachineError::IsaDmaRequiresPcPlatform => {
+                write!(f, "ISA DMA clients require enable_pc_platform=true")
+            }
+            MachineError::IsaDmaClient(err) => write!(f, "ISA DMA client: {err}"),
+            MachineError::InvalidNvram(err) => write!(f, "invalid NVRAM blob: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MachineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MachineError::Disk(err) => Some(err),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for MachineError {}
+impl From<aero_storage::DiskError> for MachineError {
+    fn from(err: aero_storage::DiskError) -> Self {
+        MachineError::Disk(err)
+    }
+}
 
 struct SystemMemory {
     a20: A20GateHandle,
     bus: PlatformMemoryBus,
     dirty: DirtyTracker,
     mapped_roms: HashMap<u64, usize>,
-    mapped_mmio: Vec<(u64, u64)>,
+    /// `(start, end_exclusive, owner)` of every mapping made via [`SystemMemory::map_mmio_once`].
+    mapped_mmio: Vec<(u64, u64, &'static str)>,
 }
 
 impl SystemMemory {
+    /// Owners of MMIO mappings that intersect `[start, end)`.
+    fn mmio_owners_in(&self, start: u64, end: u64) -> impl Iterator<Item = &'static str> + '_ {
+        self.mapped_mmio
+            .iter()
+            .filter(move |&&(s, e, _)| s < end && start < e)
+            .map(|&(_, _, owner)| owner)
+    }
+
     // Reset/mapping strategy (Strategy C: idempotent mapping helpers):
     //
     // - The physical memory bus (RAM + ROM + MMIO routing) is constructed once in `Machine::new`
@@ -1233,10 +1872,19 @@ impl SystemMemory {
         })
     }
 
+    /// Bus-master view of guest memory for device DMA, which is not subject to the A20 gate.
+    fn bus_master(&mut self) -> BusMasterMemory<'_> {
+        self.bus.bus_master()
+    }
+
     fn take_dirty_pages(&mut self) -> Vec<u64> {
         self.dirty.take_dirty_pages()
     }
 
+    fn take_sampled_dirty_bitmap(&mut self) -> Option<Vec<u64>> {
+        self.dirty.take_sampled_bitmap()
+    }
+
     fn clear_dirty(&mut self) {
         self.dirty.clear_dirty();
     }
@@ -1247,11 +1895,30 @@ impl SystemMemory {
     /// are expected to be persistent. Callers may invoke this during every reset; identical
     /// mappings are treated as idempotent, while unexpected overlaps still panic to avoid silently
     /// corrupting the address space.
+    ///
+    /// `owner` names the device model behind the mapping for debug views such as
+    /// [`Machine::debug_resource_map`].
     #[allow(dead_code)]
     #[track_caller]
-    fn map_mmio_once<F>(&mut self, start: u64, len: u64, build: F)
+    fn map_mmio_once<F>(&mut self, owner: &'static str, start: u64, len: u64, build: F)
     where
         F: FnOnce() -> Box<dyn memory::MmioHandler>,
+    {
+        self.map_mmio_once_with_policy(owner, start, len, memory::AccessPolicy::Passthrough, build);
+    }
+
+    /// Like [`SystemMemory::map_mmio_once`], but accesses are legalized through `policy` before
+    /// they reach the handler.
+    #[track_caller]
+    fn map_mmio_once_with_policy<F>(
+        &mut self,
+        owner: &'static str,
+        start: u64,
+        len: u64,
+        policy: memory::AccessPolicy,
+        build: F,
+    ) where
+        F: FnOnce() -> Box<dyn memory::MmioHandler>,
     {
         if len == 0 {
             return;
@@ -1266,14 +1933,14 @@ impl SystemMemory {
         if self
             .mapped_mmio
             .iter()
-            .any(|&(s, e)| s == start && e == end)
+            .any(|&(s, e, _)| s == start && e == end)
         {
             return;
         }
 
         let handler = build();
-        match self.bus.map_mmio(start, len, handler) {
-            Ok(()) => self.mapped_mmio.push((start, end)),
+        match self.bus.map_mmio_with_policy(start, len, policy, handler) {
+            Ok(()) => self.mapped_mmio.push((start, end, owner)),
             Err(MapError::Overlap) => {
                 // This should not happen for well-behaved callers because we short-circuit based
                 // on `mapped_mmio` above. If it does, something attempted to create a conflicting
@@ -1489,6 +2156,8 @@ struct PerCpuSystemMemoryBus<'a> {
     interrupts: Option<Rc<RefCell<PlatformInterrupts>>>,
     ap_cpus: ApCpus<'a>,
     mem: &'a mut SystemMemory,
+    // Guest LAPIC access counter; `None` for host-initiated debug accesses.
+    lapic_perf: Option<&'a perf::MmioCounter>,
 }
 
 impl<'a> PerCpuSystemMemoryBus<'a> {
@@ -1497,12 +2166,14 @@ impl<'a> PerCpuSystemMemoryBus<'a> {
         interrupts: Option<Rc<RefCell<PlatformInterrupts>>>,
         ap_cpus: ApCpus<'a>,
         mem: &'a mut SystemMemory,
+        lapic_perf: Option<&'a perf::MmioCounter>,
     ) -> Self {
         Self {
             apic_id,
             interrupts,
             ap_cpus,
             mem,
+            lapic_perf,
         }
     }
 
@@ -1735,6 +2406,9 @@ impl memory::MemoryBus for PerCpuSystemMemoryBus<'_> {
             let remaining = buf.len() - offset;
             if paddr >= lapic_start && paddr < lapic_end {
                 let chunk_len = ((lapic_end - paddr) as usize).min(remaining);
+                if let Some(counter) = self.lapic_perf {
+                    counter.record_read();
+                }
                 interrupts.borrow().lapic_mmio_read_for_apic(
                     self.apic_id,
                     paddr - lapic_start,
@@ -1780,6 +2454,9 @@ impl memory::MemoryBus for PerCpuSystemMemoryBus<'_> {
             if paddr >= lapic_start && paddr < lapic_end {
                 let chunk_len = ((lapic_end - paddr) as usize).min(remaining);
                 let lapic_offset = paddr - lapic_start;
+                if let Some(counter) = self.lapic_perf {
+                    counter.record_write();
+                }
                 interrupts.borrow().lapic_mmio_write_for_apic(
                     self.apic_id,
                     lapic_offset,
@@ -1880,13 +2557,17 @@ impl aero_mmu::MemoryBus for PerCpuSystemMemoryBus<'_> {
 
 struct StrictIoPortBus<'a> {
     io: &'a mut IoPortBus,
+    counts: &'a mut AccessCounts,
 }
 
 impl aero_cpu_core::paging_bus::IoBus for StrictIoPortBus<'_> {
     fn io_read(&mut self, port: u16, size: u32) -> Result<u64, Exception> {
         match size {
             0 => Ok(0),
-            1 | 2 | 4 => Ok(u64::from(self.io.read(port, size as u8))),
+            1 | 2 | 4 => {
+                perf::bump(&mut self.counts.reads);
+                Ok(u64::from(self.io.read(port, size as u8)))
+            }
             _ => Err(Exception::InvalidOpcode),
         }
     }
@@ -1895,6 +2576,7 @@ impl aero_cpu_core::paging_bus::IoBus for StrictIoPortBus<'_> {
         match size {
             0 => Ok(()),
             1 | 2 | 4 => {
+                perf::bump(&mut self.counts.writes);
                 self.io.write(port, size as u8, val as u32);
                 Ok(())
             }
@@ -1903,15 +2585,51 @@ impl aero_cpu_core::paging_bus::IoBus for StrictIoPortBus<'_> {
     }
 }
 
+/// Guest time in nanoseconds: the platform clock when the PC platform is enabled, otherwise the
+/// TSC scaled by its frequency.
+fn guest_now_ns(clock: Option<&ManualClock>, tsc: u64, tsc_hz: u64) -> u64 {
+    if let Some(clock) = clock {
+        return clock.now_ns();
+    }
+    if tsc_hz == 0 {
+        return 0;
+    }
+    (u128::from(tsc) * 1_000_000_000 / u128::from(tsc_hz)) as u64
+}
+
+/// Guest access path to the aero-bench device.
+///
+/// The device needs guest memory and the issuing vCPU's TSC, neither of which a
+/// [`PortIoDevice`](aero_platform::io::PortIoDevice) sees, so `MachineCpuBus` decodes its ports
+/// ahead of the port I/O bus.
+struct BenchPort<'a> {
+    dev: &'a mut BenchDevice,
+    clock: Option<&'a ManualClock>,
+    tsc_hz: u64,
+    // Issuing vCPU's TSC as of the last `CpuBus::sync`.
+    tsc: u64,
+}
+
 struct MachineCpuBus<'a> {
     a20: A20GateHandle,
     reset: ResetLatch,
     inner: aero_cpu_core::PagingBus<PerCpuSystemMemoryBus<'a>, StrictIoPortBus<'a>>,
+    bench: Option<BenchPort<'a>>,
+    unknown_access: &'a unknown_access::UnknownAccessTracker,
+    // `None` unless a code diagnostic is enabled.
+    code_diagnostics: Option<&'a code_diagnostics::CodeDiagnostics>,
 }
 
 impl aero_cpu_core::mem::CpuBus for MachineCpuBus<'_> {
     #[inline]
     fn sync(&mut self, state: &CpuState) {
+        if let Some(bench) = self.bench.as_mut() {
+            bench.tsc = state.msr.tsc;
+        }
+        self.unknown_access.enter_cpu(state.rip());
+        if let Some(diagnostics) = self.code_diagnostics {
+            diagnostics.enter_cpu(state.rip());
+        }
         self.inner.sync(state);
     }
 
@@ -2038,16 +2756,50 @@ impl aero_cpu_core::mem::CpuBus for MachineCpuBus<'_> {
         if self.reset.peek().is_some() {
             return Err(Exception::Unimplemented("reset requested"));
         }
-        self.inner.fetch(vaddr, max_len)
+        let bytes = self.inner.fetch(vaddr, max_len)?;
+        if let Some(diagnostics) = self.code_diagnostics.filter(|d| d.smc_enabled()) {
+            // Only the page holding the first byte is recorded: `max_len` is a prefetch window,
+            // not the instruction length.
+            if let Some(paddr) = self.inner.probe_fetch_paddr(vaddr) {
+                let paddr = if self.a20.enabled() {
+                    paddr
+                } else {
+                    paddr & !(1 << 20)
+                };
+                diagnostics.record_fetch(paddr);
+            }
+        }
+        Ok(bytes)
     }
 
     #[inline]
     fn io_read(&mut self, port: u16, size: u32) -> Result<u64, Exception> {
+        if let Some(bench) = self.bench.as_ref() {
+            if matches!(size, 1 | 2 | 4) && BenchDevice::decodes(port) {
+                perf::bump(&mut self.inner.io_mut().counts.reads);
+                return Ok(u64::from(bench.dev.read(port, size as u8)));
+            }
+        }
         self.inner.io_read(port, size)
     }
 
     #[inline]
     fn io_write(&mut self, port: u16, size: u32, val: u64) -> Result<(), Exception> {
+        if let Some(bench) = self.bench.as_mut() {
+            if matches!(size, 1 | 2 | 4) && BenchDevice::decodes(port) {
+                perf::bump(&mut self.inner.io_mut().counts.writes);
+                let now_ns = guest_now_ns(bench.clock, bench.tsc, bench.tsc_hz);
+                bench.dev.write(
+                    port,
+                    size as u8,
+                    val as u32,
+                    bench.tsc,
+                    now_ns,
+                    self.inner.inner_mut(),
+                );
+                return Ok(());
+            }
+        }
         self.inner.io_write(port, size, val)
     }
 }
@@ -2113,8 +2865,16 @@ struct AeroGpuPciConfigDevice {
 }
 
 impl AeroGpuPciConfigDevice {
-    fn new() -> Self {
-        let cfg = aero_devices::pci::profile::AEROGPU.build_config_space();
+    /// `vram_bar_size` overrides the profile's BAR1 size (see
+    /// [`MachineConfig::aerogpu_vram_size_bytes`]); it must be a power of two.
+    ///
+    /// Apertures larger than the profile default do not fit next to the other devices in the BIOS
+    /// POST allocator window, so BAR1 is pre-assigned at [`PCI_MMIO_BASE`] instead: the space
+    /// between the end of ECAM and the allocator window is inside the ACPI-reported PCI MMIO
+    /// window and naturally aligned for any size up to [`AEROGPU_VRAM_SIZE_MAX`]. POST preserves
+    /// pre-assigned BAR bases.
+    fn new(vram_bar_size: u64) -> Self {
+        let mut cfg = aero_devices::pci::profile::AEROGPU.build_config_space();
         debug_assert_eq!(
             cfg.bar_definition(aero_devices::pci::profile::AEROGPU_BAR0_INDEX),
             Some(PciBarDefinition::Mmio32 {
@@ -2133,6 +2893,24 @@ impl AeroGpuPciConfigDevice {
             }),
             "unexpected AeroGPU BAR1 definition"
         );
+        cfg.set_bar_definition(
+            aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
+            PciBarDefinition::Mmio32 {
+                size: u32::try_from(vram_bar_size).expect("AeroGPU VRAM size should fit in u32"),
+                prefetchable: true,
+            },
+        );
+        if vram_bar_size > aero_devices::pci::profile::AEROGPU_VRAM_SIZE {
+            debug_assert!(
+                PCI_MMIO_BASE + AEROGPU_VRAM_SIZE_MAX as u64
+                    <= PciResourceAllocatorConfig::default().mmio_base,
+                "pinned AeroGPU BAR1 must not overlap the BIOS POST allocator window"
+            );
+            cfg.set_bar_base(
+                aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
+                PCI_MMIO_BASE,
+            );
+        }
         Self { cfg }
     }
 }
@@ -2174,9 +2952,11 @@ struct VirtioNetPciConfigDevice {
 }
 
 impl VirtioNetPciConfigDevice {
-    fn new() -> Self {
+    fn new(transport: VirtioPciTransport) -> Self {
         Self {
-            cfg: aero_devices::pci::profile::VIRTIO_NET.build_config_space(),
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_NET)
+                .build_config_space(),
         }
     }
 }
@@ -2196,9 +2976,11 @@ struct VirtioInputKeyboardPciConfigDevice {
 }
 
 impl VirtioInputKeyboardPciConfigDevice {
-    fn new() -> Self {
+    fn new(transport: VirtioPciTransport) -> Self {
         Self {
-            cfg: aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD.build_config_space(),
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD)
+                .build_config_space(),
         }
     }
 }
@@ -2218,9 +3000,11 @@ struct VirtioInputMousePciConfigDevice {
 }
 
 impl VirtioInputMousePciConfigDevice {
-    fn new() -> Self {
+    fn new(transport: VirtioPciTransport) -> Self {
         Self {
-            cfg: aero_devices::pci::profile::VIRTIO_INPUT_MOUSE.build_config_space(),
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_INPUT_MOUSE)
+                .build_config_space(),
         }
     }
 }
@@ -2240,9 +3024,11 @@ struct VirtioInputTabletPciConfigDevice {
 }
 
 impl VirtioInputTabletPciConfigDevice {
-    fn new() -> Self {
+    fn new(transport: VirtioPciTransport) -> Self {
         Self {
-            cfg: aero_devices::pci::profile::VIRTIO_INPUT_TABLET.build_config_space(),
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_INPUT_TABLET)
+                .build_config_space(),
         }
     }
 }
@@ -2257,15 +3043,96 @@ impl PciDevice for VirtioInputTabletPciConfigDevice {
     }
 }
 
+struct VirtioBalloonPciConfigDevice {
+    cfg: aero_devices::pci::PciConfigSpace,
+}
+
+impl VirtioBalloonPciConfigDevice {
+    fn new(transport: VirtioPciTransport) -> Self {
+        Self {
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_BALLOON)
+                .build_config_space(),
+        }
+    }
+}
+
+impl PciDevice for VirtioBalloonPciConfigDevice {
+    fn config(&self) -> &aero_devices::pci::PciConfigSpace {
+        &self.cfg
+    }
+
+    fn config_mut(&mut self) -> &mut aero_devices::pci::PciConfigSpace {
+        &mut self.cfg
+    }
+}
+
+struct Virtio9pPciConfigDevice {
+    cfg: aero_devices::pci::PciConfigSpace,
+}
+
+impl Virtio9pPciConfigDevice {
+    fn new(transport: VirtioPciTransport) -> Self {
+        Self {
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_9P)
+                .build_config_space(),
+        }
+    }
+}
+
+impl PciDevice for Virtio9pPciConfigDevice {
+    fn config(&self) -> &aero_devices::pci::PciConfigSpace {
+        &self.cfg
+    }
+
+    fn config_mut(&mut self) -> &mut aero_devices::pci::PciConfigSpace {
+        &mut self.cfg
+    }
+}
+
+struct VirtioRngPciConfigDevice {
+    cfg: aero_devices::pci::PciConfigSpace,
+}
+
+impl VirtioRngPciConfigDevice {
+    fn new(transport: VirtioPciTransport) -> Self {
+        Self {
+            cfg: transport
+                .pci_profile(aero_devices::pci::profile::VIRTIO_RNG)
+                .build_config_space(),
+        }
+    }
+}
+
+impl PciDevice for VirtioRngPciConfigDevice {
+    fn config(&self) -> &aero_devices::pci::PciConfigSpace {
+        &self.cfg
+    }
+
+    fn config_mut(&mut self) -> &mut aero_devices::pci::PciConfigSpace {
+        &mut self.cfg
+    }
+}
+
 struct VirtioBlkPciConfigDevice {
     cfg: aero_devices::pci::PciConfigSpace,
 }
 
 impl VirtioBlkPciConfigDevice {
-    fn new() -> Self {
-        Self {
-            cfg: aero_devices::pci::profile::VIRTIO_BLK.build_config_space(),
+    fn new(num_queues: u16, transport: VirtioPciTransport) -> Self {
+        use aero_devices::pci::profile;
+
+        // The canonical profile sizes the MSI-X table for a single queue; rebuild the capability
+        // so every request queue gets a vector (matching the device model's own config space).
+        let mut cfg = profile::PciDeviceProfile {
+            capabilities: &profile::VIRTIO_VENDOR_CAPS,
+            ..transport.pci_profile(profile::VIRTIO_BLK)
         }
+        .build_config_space();
+        profile::virtio_msix_capability_profile(usize::from(num_queues), profile::VIRTIO_BAR0_SIZE)
+            .add_to_config_space(&mut cfg);
+        Self { cfg }
     }
 }
 
@@ -2345,6 +3212,33 @@ fn sync_virtio_msix_from_platform(dev: &mut VirtioPciDevice, enabled: bool, func
     }
 }
 
+/// Mirrors the canonical PCI COMMAND register and MSI-X enable/mask bits into a virtio transport
+/// before a BAR access, so decode gating and INTx/MSI-X selection match the guest-visible state.
+fn sync_virtio_pci_command(
+    pci_cfg: &SharedPciConfigPorts,
+    bdf: PciBdf,
+    dev: &Rc<RefCell<VirtioPciDevice>>,
+) {
+    let (command, msix_enabled, msix_masked) = {
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        match pci_cfg.bus_mut().device_config(bdf) {
+            Some(cfg) => {
+                let msix = cfg.capability::<MsixCapability>();
+                (
+                    cfg.command(),
+                    msix.is_some_and(|msix| msix.enabled()),
+                    msix.is_some_and(|msix| msix.function_masked()),
+                )
+            }
+            None => (0, false, false),
+        }
+    };
+
+    let mut dev = dev.borrow_mut();
+    dev.set_pci_command(command);
+    sync_virtio_msix_from_platform(&mut dev, msix_enabled, msix_masked);
+}
+
 struct VirtioPciBar0Mmio {
     pci_cfg: SharedPciConfigPorts,
     bdf: PciBdf,
@@ -2357,25 +3251,8 @@ impl VirtioPciBar0Mmio {
     }
 
     fn sync_pci_command(&mut self) {
-        let (command, msix_enabled, msix_masked) = {
-            let mut pci_cfg = self.pci_cfg.borrow_mut();
-            match pci_cfg.bus_mut().device_config(self.bdf) {
-                Some(cfg) => {
-                    let msix = cfg.capability::<MsixCapability>();
-                    (
-                        cfg.command(),
-                        msix.is_some_and(|msix| msix.enabled()),
-                        msix.is_some_and(|msix| msix.function_masked()),
-                    )
-                }
-                None => (0, false, false),
-            }
-        };
-
-        let mut dev = self.dev.borrow_mut();
-        dev.set_pci_command(command);
-        sync_virtio_msix_from_platform(&mut dev, msix_enabled, msix_masked);
-    }
+        sync_virtio_pci_command(&self.pci_cfg, self.bdf, &self.dev);
+    }
 
     fn all_ones(size: usize) -> u64 {
         if size == 0 {
@@ -2411,29 +3288,52 @@ impl PciBarMmioHandler for VirtioPciBar0Mmio {
     }
 }
 
+/// Legacy (virtio 0.9) register block of a transitional virtio-pci device (I/O BAR2).
+///
+/// Reading the ISR register acknowledges the interrupt and clears the device's INTx latch. The
+/// platform line is only lowered by the next [`Machine::sync_pci_intx_sources_to_interrupts`],
+/// which runs before every interrupt controller poll, so a line acknowledged this way is never
+/// redelivered.
+struct VirtioPciLegacyIoBar {
+    pci_cfg: SharedPciConfigPorts,
+    bdf: PciBdf,
+    dev: Rc<RefCell<VirtioPciDevice>>,
+}
+
+impl PciIoBarHandler for VirtioPciLegacyIoBar {
+    fn io_read(&mut self, offset: u64, size: usize) -> u32 {
+        sync_virtio_pci_command(&self.pci_cfg, self.bdf, &self.dev);
+        let mut buf = [0u8; 4];
+        let size = size.min(buf.len());
+        self.dev
+            .borrow_mut()
+            .legacy_io_read(offset, &mut buf[..size]);
+        u32::from_le_bytes(buf)
+    }
+
+    fn io_write(&mut self, offset: u64, size: usize, value: u32) {
+        sync_virtio_pci_command(&self.pci_cfg, self.bdf, &self.dev);
+        let bytes = value.to_le_bytes();
+        let size = size.min(bytes.len());
+        self.dev
+            .borrow_mut()
+            .legacy_io_write(offset, &bytes[..size]);
+    }
+}
+
 /// Guest-memory adapter to allow virtio devices to DMA against the canonical [`SystemMemory`] RAM.
 ///
 /// Virtio queue descriptor addresses are guest-physical. We intentionally DMA only against guest
-/// RAM (not ROM/MMIO) and apply A20 masking when disabled so legacy behavior remains consistent.
+/// RAM (not ROM/MMIO). Like all bus-master DMA, these accesses are not subject to the A20 gate.
+/// `PlatformMemoryBus::ram` is the high-memory remapped view, so addresses above 4GiB reach the
+/// remapped RAM and the ECAM/PCI hole reads as open bus.
 struct VirtioDmaMemory<'a> {
-    a20_enabled: bool,
     bus: &'a mut PlatformMemoryBus,
 }
 
 impl<'a> VirtioDmaMemory<'a> {
     fn new(mem: &'a mut SystemMemory) -> Self {
-        Self {
-            a20_enabled: mem.a20.enabled(),
-            bus: &mut mem.bus,
-        }
-    }
-
-    fn translate_a20(&self, addr: u64) -> u64 {
-        if self.a20_enabled {
-            addr
-        } else {
-            addr & !(1u64 << 20)
-        }
+        Self { bus: &mut mem.bus }
     }
 }
 
@@ -2446,53 +3346,26 @@ impl VirtioGuestMemory for VirtioDmaMemory<'_> {
         if dst.is_empty() {
             return Ok(());
         }
-        if self.a20_enabled {
-            self.bus.ram().read_into(addr, dst).map_err(|_| {
-                VirtioGuestMemoryError::OutOfBounds {
-                    addr,
-                    len: dst.len(),
-                }
-            })?;
-            return Ok(());
-        }
-
-        // A20-disabled slow path: translate each byte so accesses that cross the 1MiB boundary wrap
-        // like real hardware.
-        for (i, slot) in dst.iter_mut().enumerate() {
-            let a = self.translate_a20(addr.wrapping_add(i as u64));
-            let mut tmp = [0u8; 1];
-            self.bus
-                .ram()
-                .read_into(a, &mut tmp)
-                .map_err(|_| VirtioGuestMemoryError::OutOfBounds { addr: a, len: 1 })?;
-            *slot = tmp[0];
-        }
-        Ok(())
+        self.bus
+            .ram()
+            .read_into(addr, dst)
+            .map_err(|_| VirtioGuestMemoryError::OutOfBounds {
+                addr,
+                len: dst.len(),
+            })
     }
 
     fn write(&mut self, addr: u64, src: &[u8]) -> Result<(), VirtioGuestMemoryError> {
         if src.is_empty() {
             return Ok(());
         }
-        if self.a20_enabled {
-            self.bus.ram_mut().write_from(addr, src).map_err(|_| {
-                VirtioGuestMemoryError::OutOfBounds {
-                    addr,
-                    len: src.len(),
-                }
-            })?;
-            return Ok(());
-        }
-
-        // A20-disabled slow path: translate each byte individually.
-        for (i, byte) in src.iter().copied().enumerate() {
-            let a = self.translate_a20(addr.wrapping_add(i as u64));
-            self.bus
-                .ram_mut()
-                .write_from(a, &[byte])
-                .map_err(|_| VirtioGuestMemoryError::OutOfBounds { addr: a, len: 1 })?;
-        }
-        Ok(())
+        self.bus
+            .ram_mut()
+            .write_from(addr, src)
+            .map_err(|_| VirtioGuestMemoryError::OutOfBounds {
+                addr,
+                len: src.len(),
+            })
     }
 }
 struct Piix3IsaPciConfigDevice {
@@ -2500,10 +3373,19 @@ struct Piix3IsaPciConfigDevice {
 }
 
 impl Piix3IsaPciConfigDevice {
-    fn new() -> Self {
-        Self {
-            cfg: aero_devices::pci::profile::ISA_PIIX3.build_config_space(),
+    fn new(pirq_to_gsi: [u32; 4]) -> Self {
+        let mut cfg = aero_devices::pci::profile::ISA_PIIX3.build_config_space();
+        // PIRQRC[A:D] power on with the platform's PIRQ-to-IRQ table. The guest may reprogram
+        // them; `Machine::sync_pci_intx_sources_to_interrupts` applies the live values to the
+        // INTx router. PIRQs hardwired to GSIs >= 16 read back as disabled.
+        for (pirq, gsi) in pirq_to_gsi.into_iter().enumerate() {
+            cfg.write(
+                aero_devices::pci::PIIX3_PIRQ_ROUTE_OFFSET + pirq as u16,
+                1,
+                u32::from(aero_devices::pci::piix3_pirq_route_from_gsi(Some(gsi))),
+            );
         }
+        Self { cfg }
     }
 }
 
@@ -2710,11 +3592,18 @@ const _: () = {
 // The current AeroGPU legacy VGA/VBE compatibility path only requires the low portion of VRAM
 // (legacy window + VBE LFB). Allocate a smaller backing store on wasm32 to keep the canonical
 // browser `Machine::new` usable in constrained test environments.
+//
+// `MachineConfig::aerogpu_vram_size_bytes` overrides both the BAR size and this allocation.
 #[cfg(target_arch = "wasm32")]
 const AEROGPU_VRAM_ALLOC_SIZE: usize = 32 * 1024 * 1024;
 #[cfg(not(target_arch = "wasm32"))]
 const AEROGPU_VRAM_ALLOC_SIZE: usize = aero_devices::pci::profile::AEROGPU_VRAM_SIZE as usize;
 
+/// Smallest accepted [`MachineConfig::aerogpu_vram_size_bytes`].
+pub const AEROGPU_VRAM_SIZE_MIN: usize = 16 * 1024 * 1024;
+/// Largest accepted [`MachineConfig::aerogpu_vram_size_bytes`].
+pub const AEROGPU_VRAM_SIZE_MAX: usize = 512 * 1024 * 1024;
+
 /// Minimal AeroGPU runtime state required for VRAM-backed legacy VGA/VBE compatibility.
 ///
 /// Note: The versioned BAR0 MMIO register block (ring transport + scanout + vblank pacing) is
@@ -2775,14 +3664,7 @@ struct AeroGpuDevice {
     // ---------------------------------------------------------------------
     // VGA DAC / palette (0x3C6..=0x3C9)
     // ---------------------------------------------------------------------
-    pel_mask: u8,
-    dac_write_index: u8,
-    dac_write_subindex: u8,
-    dac_write_latch: [u8; 3],
-    dac_read_index: u8,
-    dac_read_subindex: u8,
-    /// Stored as VGA-native 6-bit components (0..=63).
-    dac_palette: [[u8; 3]; 256],
+    dac: VgaDac,
 }
 
 impl AeroGpuDevice {
@@ -2794,55 +3676,6 @@ impl AeroGpuDevice {
         self.vbe_mode_active || self.vbe_dispi_enabled()
     }
 
-    fn default_dac_palette() -> [[u8; 3]; 256] {
-        let mut out = [[0u8; 3]; 256];
-        // Map an 8-bit (0..=255) channel value to a VGA 6-bit DAC value (0..=63) with rounding.
-        let to_6bit = |v: u8| -> u8 { ((u16::from(v) * 63 + 127) / 255) as u8 };
-
-        // Standard EGA 16-color palette encoded as VGA DAC 6-bit components.
-        //
-        // This matches the VGA device model's default palette for indices 0..15.
-        const EGA_6BIT: [[u8; 3]; 16] = [
-            [0, 0, 0],    // 0 black
-            [0, 0, 42],   // 1 blue
-            [0, 42, 0],   // 2 green
-            [0, 42, 42],  // 3 cyan
-            [42, 0, 0],   // 4 red
-            [42, 0, 42],  // 5 magenta
-            [42, 21, 0],  // 6 brown
-            [42, 42, 42], // 7 light grey
-            [21, 21, 21], // 8 dark grey
-            [21, 21, 63], // 9 bright blue
-            [21, 63, 21], // 10 bright green
-            [21, 63, 63], // 11 bright cyan
-            [63, 21, 21], // 12 bright red
-            [63, 21, 63], // 13 bright magenta
-            [63, 63, 21], // 14 yellow
-            [63, 63, 63], // 15 white
-        ];
-        out[..16].copy_from_slice(&EGA_6BIT);
-
-        // 6x6x6 color cube (indices 16..231), similar to the classic VGA palette.
-        let mut idx = 16usize;
-        for r in 0..6u8 {
-            for g in 0..6u8 {
-                for b in 0..6u8 {
-                    let scale8 = |v: u8| -> u8 { ((u16::from(v) * 255) / 5) as u8 };
-                    out[idx] = [to_6bit(scale8(r)), to_6bit(scale8(g)), to_6bit(scale8(b))];
-                    idx += 1;
-                }
-            }
-        }
-
-        // Grayscale ramp (232..255).
-        for i in 0..24u8 {
-            let v8 = ((u16::from(i) * 255) / 23) as u8;
-            let v6 = to_6bit(v8);
-            out[232 + i as usize] = [v6, v6, v6];
-        }
-        out
-    }
-
     fn default_attr_regs() -> [u8; 256] {
         // VGA text mode defaults (matching `aero_gpu_vga::VgaDevice::set_text_mode_80x25`):
         // - Mode Control: bit0=0 => text; bit2=line graphics enable.
@@ -2859,19 +3692,19 @@ impl AeroGpuDevice {
         regs
     }
 
-    fn new() -> Self {
+    fn new(vram_size: usize) -> Self {
         // `vec![0; len]` aborts the process on OOM. VRAM backing is an implementation detail (the
-        // guest-visible BAR aperture size remains fixed), so allocate fallibly and fall back to a
-        // smaller buffer instead of crashing.
+        // guest-visible BAR aperture size is fixed by the PCI config space), so allocate fallibly
+        // and fall back to a smaller buffer instead of crashing.
         let mut vram = Vec::new();
-        if vram.try_reserve_exact(AEROGPU_VRAM_ALLOC_SIZE).is_ok() {
-            vram.resize(AEROGPU_VRAM_ALLOC_SIZE, 0u8);
+        if vram.try_reserve_exact(vram_size).is_ok() {
+            vram.resize(vram_size, 0u8);
         } else {
             // Best-effort fallbacks: prioritize preserving the legacy VGA window and Bochs VBE
             // register-visible VRAM prefix for boot output.
             let candidates = [
-                VBE_LFB_OFFSET.min(AEROGPU_VRAM_ALLOC_SIZE),
-                LEGACY_VGA_WINDOW_SIZE.min(AEROGPU_VRAM_ALLOC_SIZE),
+                VBE_LFB_OFFSET.min(vram_size),
+                LEGACY_VGA_WINDOW_SIZE.min(vram_size),
                 0usize,
             ];
             for &len in &candidates {
@@ -2910,18 +3743,17 @@ impl AeroGpuDevice {
             attr_index: 0,
             attr_regs: Self::default_attr_regs(),
             attr_flip_flop: false,
-            pel_mask: 0xFF,
-            dac_write_index: 0,
-            dac_write_subindex: 0,
-            dac_write_latch: [0; 3],
-            dac_read_index: 0,
-            dac_read_subindex: 0,
-            dac_palette: Self::default_dac_palette(),
+            dac: VgaDac::new(),
         }
     }
 
     fn reset(&mut self) {
         self.vram.fill(0);
+        self.reset_preserving_vram();
+    }
+
+    /// Reset all device state except VRAM contents (warm reset).
+    fn reset_preserving_vram(&mut self) {
         self.vram_mmio_reads.set(0);
         self.vbe_mode_active = false;
         self.vbe_bank = 0;
@@ -2945,13 +3777,7 @@ impl AeroGpuDevice {
         self.attr_index = 0;
         self.attr_regs = Self::default_attr_regs();
         self.attr_flip_flop = false;
-        self.pel_mask = 0xFF;
-        self.dac_write_index = 0;
-        self.dac_write_subindex = 0;
-        self.dac_write_latch = [0; 3];
-        self.dac_read_index = 0;
-        self.dac_read_subindex = 0;
-        self.dac_palette = Self::default_dac_palette();
+        self.dac = VgaDac::new();
     }
 
     fn vbe_dispi_read_reg(&self, index: u16) -> u16 {
@@ -3009,44 +3835,6 @@ impl AeroGpuDevice {
         }
     }
 
-    fn write_dac_data(&mut self, value: u8) {
-        let idx = self.dac_write_index as usize;
-        let component = (self.dac_write_subindex as usize) % 3;
-        self.dac_write_latch[component] = value;
-        self.dac_write_subindex = (self.dac_write_subindex + 1) % 3;
-        if self.dac_write_subindex != 0 {
-            return;
-        }
-
-        // Real VGA hardware uses a 6-bit DAC (0..=63), but a lot of software writes 8-bit values.
-        // Be permissive by detecting 8-bit mode per RGB triplet.
-        let is_8bit = self.dac_write_latch.iter().any(|&v| v > 0x3F);
-        let to_6bit = |v: u8| -> u8 {
-            if is_8bit {
-                v >> 2
-            } else {
-                v & 0x3F
-            }
-        };
-
-        let r = to_6bit(self.dac_write_latch[0]);
-        let g = to_6bit(self.dac_write_latch[1]);
-        let b = to_6bit(self.dac_write_latch[2]);
-        self.dac_palette[idx] = [r, g, b];
-        self.dac_write_index = self.dac_write_index.wrapping_add(1);
-    }
-
-    fn read_dac_data(&mut self) -> u8 {
-        let idx = self.dac_read_index as usize;
-        let component = (self.dac_read_subindex as usize).min(2);
-        let out = self.dac_palette[idx][component];
-        self.dac_read_subindex = (self.dac_read_subindex + 1) % 3;
-        if self.dac_read_subindex == 0 {
-            self.dac_read_index = self.dac_read_index.wrapping_add(1);
-        }
-        out
-    }
-
     fn read_linear(buf: &[u8], offset: u64, size: usize) -> u64 {
         if size == 0 {
             return 0;
@@ -3192,10 +3980,7 @@ impl AeroGpuDevice {
             // Attribute controller: reads happen via 0x3C1.
             0x03C1 => self.attr_regs[usize::from(self.attr_index)],
             // VGA DAC.
-            0x03C6 => self.pel_mask,
-            0x03C7 => self.dac_read_index,
-            0x03C8 => self.dac_write_index,
-            0x03C9 => self.read_dac_data(),
+            0x03C6..=0x03C9 => self.dac.port_read(port),
             // Misc Output readback (0x3CC).
             //
             // Real hardware reads Misc Output at 0x3CC (and 0x3C2 is Input Status 0). Accept reads
@@ -3241,16 +4026,9 @@ impl AeroGpuDevice {
             0x03C2 => self.misc_output = value,
 
             // VGA DAC.
-            0x03C6 => self.pel_mask = value,
-            0x03C7 => {
-                self.dac_read_index = value;
-                self.dac_read_subindex = 0;
-            }
-            0x03C8 => {
-                self.dac_write_index = value;
-                self.dac_write_subindex = 0;
+            0x03C6..=0x03C9 => {
+                self.dac.port_write(port, value);
             }
-            0x03C9 => self.write_dac_data(value),
 
             // Sequencer.
             0x03C4 => self.seq_index = value,
@@ -3299,13 +4077,8 @@ impl IoSnapshot for AeroGpuDevice {
         const TAG_VGA_ATTR_REGS: u16 = 109;
         const TAG_VGA_ATTR_FLIP_FLOP: u16 = 110;
 
-        const TAG_VGA_PEL_MASK: u16 = 120;
-        const TAG_VGA_DAC_WRITE_INDEX: u16 = 121;
-        const TAG_VGA_DAC_WRITE_SUBINDEX: u16 = 122;
-        const TAG_VGA_DAC_WRITE_LATCH: u16 = 123;
-        const TAG_VGA_DAC_READ_INDEX: u16 = 124;
-        const TAG_VGA_DAC_READ_SUBINDEX: u16 = 125;
-        const TAG_VGA_DAC_PALETTE: u16 = 126;
+        // Tags 120..=126 held the DAC registers before `TAG_VGA_DAC_STATE`.
+        const TAG_VGA_DAC_STATE: u16 = 127;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -3339,18 +4112,8 @@ impl IoSnapshot for AeroGpuDevice {
         w.field_bytes(TAG_VGA_ATTR_REGS, self.attr_regs.to_vec());
         w.field_bool(TAG_VGA_ATTR_FLIP_FLOP, self.attr_flip_flop);
 
-        w.field_u8(TAG_VGA_PEL_MASK, self.pel_mask);
-        w.field_u8(TAG_VGA_DAC_WRITE_INDEX, self.dac_write_index);
-        w.field_u8(TAG_VGA_DAC_WRITE_SUBINDEX, self.dac_write_subindex);
-        w.field_bytes(TAG_VGA_DAC_WRITE_LATCH, self.dac_write_latch.to_vec());
-        w.field_u8(TAG_VGA_DAC_READ_INDEX, self.dac_read_index);
-        w.field_u8(TAG_VGA_DAC_READ_SUBINDEX, self.dac_read_subindex);
-
-        let mut palette = Vec::with_capacity(256 * 3);
-        for rgb in &self.dac_palette {
-            palette.extend_from_slice(rgb);
-        }
-        w.field_bytes(TAG_VGA_DAC_PALETTE, palette);
+        // Same encoding as `VgaDevice`'s DAC (see `VgaDac::save_state`).
+        w.field_bytes(TAG_VGA_DAC_STATE, self.dac.save_state());
 
         w.finish()
     }
@@ -3388,6 +4151,7 @@ impl IoSnapshot for AeroGpuDevice {
         const TAG_VGA_DAC_READ_INDEX: u16 = 124;
         const TAG_VGA_DAC_READ_SUBINDEX: u16 = 125;
         const TAG_VGA_DAC_PALETTE: u16 = 126;
+        const TAG_VGA_DAC_STATE: u16 = 127;
 
         let r = IoSnapshotReader::parse(bytes, Self::DEVICE_ID)?;
         r.ensure_device_major(Self::DEVICE_VERSION.major)?;
@@ -3436,22 +4200,33 @@ impl IoSnapshot for AeroGpuDevice {
         }
         self.attr_flip_flop = r.bool(TAG_VGA_ATTR_FLIP_FLOP)?.unwrap_or(false);
 
-        self.pel_mask = r.u8(TAG_VGA_PEL_MASK)?.unwrap_or(0xFF);
-        self.dac_write_index = r.u8(TAG_VGA_DAC_WRITE_INDEX)?.unwrap_or(0);
-        self.dac_write_subindex = r.u8(TAG_VGA_DAC_WRITE_SUBINDEX)?.unwrap_or(0);
-        if let Some(latch) = r.bytes(TAG_VGA_DAC_WRITE_LATCH) {
-            let len = latch.len().min(self.dac_write_latch.len());
-            self.dac_write_latch[..len].copy_from_slice(&latch[..len]);
-        }
-        self.dac_read_index = r.u8(TAG_VGA_DAC_READ_INDEX)?.unwrap_or(0);
-        self.dac_read_subindex = r.u8(TAG_VGA_DAC_READ_SUBINDEX)?.unwrap_or(0);
-
-        if let Some(palette) = r.bytes(TAG_VGA_DAC_PALETTE) {
-            for (i, chunk) in palette.chunks(3).enumerate().take(self.dac_palette.len()) {
-                let mut rgb = [0u8; 3];
-                let len = chunk.len().min(3);
-                rgb[..len].copy_from_slice(&chunk[..len]);
-                self.dac_palette[i] = rgb;
+        if let Some(state) = r.bytes(TAG_VGA_DAC_STATE) {
+            self.dac.load_state(state).map_err(|_| {
+                aero_io_snapshot::io::state::SnapshotError::InvalidFieldEncoding("vga dac state")
+            })?;
+        } else {
+            // Older snapshots store the DAC registers individually (palette already 6-bit).
+            self.dac
+                .set_pel_mask(r.u8(TAG_VGA_PEL_MASK)?.unwrap_or(0xFF));
+            let mut latch = [0u8; 3];
+            if let Some(bytes) = r.bytes(TAG_VGA_DAC_WRITE_LATCH) {
+                let len = bytes.len().min(latch.len());
+                latch[..len].copy_from_slice(&bytes[..len]);
+            }
+            self.dac.restore_indices(
+                r.u8(TAG_VGA_DAC_READ_INDEX)?.unwrap_or(0),
+                r.u8(TAG_VGA_DAC_READ_SUBINDEX)?.unwrap_or(0),
+                r.u8(TAG_VGA_DAC_WRITE_INDEX)?.unwrap_or(0),
+                r.u8(TAG_VGA_DAC_WRITE_SUBINDEX)?.unwrap_or(0),
+                latch,
+            );
+            if let Some(palette) = r.bytes(TAG_VGA_DAC_PALETTE) {
+                for (i, chunk) in palette.chunks(3).enumerate().take(256) {
+                    let mut rgb = [0u8; 3];
+                    let len = chunk.len().min(3);
+                    rgb[..len].copy_from_slice(&chunk[..len]);
+                    self.dac.set_entry(i as u8, rgb);
+                }
             }
         }
 
@@ -3459,8 +4234,125 @@ impl IoSnapshot for AeroGpuDevice {
     }
 }
 
+/// Shared display damage counter (see [`Machine::display_generation`]).
+///
+/// Cloned into every MMIO/port adapter whose guest writes can change presented pixels. Only
+/// writes bump it; reads never do.
+#[derive(Debug, Clone, Default)]
+struct DisplayGeneration(Rc<Cell<u64>>);
+
+impl DisplayGeneration {
+    fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    fn set(&self, value: u64) {
+        self.0.set(value);
+    }
+
+    fn bump(&self) {
+        self.0.set(self.0.get().wrapping_add(1));
+    }
+}
+
+/// Wraps a display device handler so guest writes bump the machine's [`DisplayGeneration`].
+struct DisplayWriteTracker<H> {
+    inner: H,
+    generation: DisplayGeneration,
+}
+
+impl<H: MmioHandler> MmioHandler for DisplayWriteTracker<H> {
+    fn read(&mut self, offset: u64, size: usize) -> u64 {
+        self.inner.read(offset, size)
+    }
+
+    fn write(&mut self, offset: u64, size: usize, value: u64) {
+        self.inner.write(offset, size, value);
+        self.generation.bump();
+    }
+}
+
+impl<H: aero_platform::io::PortIoDevice> aero_platform::io::PortIoDevice
+    for DisplayWriteTracker<H>
+{
+    fn read(&mut self, port: u16, size: u8) -> u32 {
+        self.inner.read(port, size)
+    }
+
+    fn write(&mut self, port: u16, size: u8, value: u32) {
+        self.inner.write(port, size, value);
+        self.generation.bump();
+    }
+
+    fn debug_name(&self) -> &'static str {
+        self.inner.debug_name()
+    }
+}
+
+/// AeroGPU BAR0 adapter: forwards to the shared MMIO device and bumps the display generation for
+/// writes to the scanout0 / cursor register blocks (ring doorbells and IRQ acks do not).
+struct AeroGpuBar0Mmio {
+    pci_cfg: SharedPciConfigPorts,
+    dev: Rc<RefCell<AeroGpuMmioDevice>>,
+    generation: DisplayGeneration,
+}
+
+impl AeroGpuBar0Mmio {
+    fn write_affects_display(offset: u64) -> bool {
+        use aero_protocol::aerogpu::aerogpu_pci as pci;
+
+        let reg = u32::try_from(offset & !3).unwrap_or(u32::MAX);
+        matches!(
+            reg,
+            pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_HEIGHT
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_FORMAT
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_PITCH_BYTES
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_LO
+                | pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_HI
+                | pci::AEROGPU_MMIO_REG_CURSOR_ENABLE
+                | pci::AEROGPU_MMIO_REG_CURSOR_X
+                | pci::AEROGPU_MMIO_REG_CURSOR_Y
+                | pci::AEROGPU_MMIO_REG_CURSOR_HOT_X
+                | pci::AEROGPU_MMIO_REG_CURSOR_HOT_Y
+                | pci::AEROGPU_MMIO_REG_CURSOR_WIDTH
+                | pci::AEROGPU_MMIO_REG_CURSOR_HEIGHT
+                | pci::AEROGPU_MMIO_REG_CURSOR_FORMAT
+                | pci::AEROGPU_MMIO_REG_CURSOR_FB_GPA_LO
+                | pci::AEROGPU_MMIO_REG_CURSOR_FB_GPA_HI
+                | pci::AEROGPU_MMIO_REG_CURSOR_PITCH_BYTES
+        )
+    }
+}
+
+impl PciBarMmioHandler for AeroGpuBar0Mmio {
+    fn read(&mut self, offset: u64, size: usize) -> u64 {
+        let mut dev = self.dev.borrow_mut();
+        // MSI-X enable/mask live in the canonical config space; mirror them before the access so
+        // table writes observe the current function mask.
+        sync_aerogpu_pci_state_into_mmio(&self.pci_cfg, &mut dev);
+        PciBarMmioHandler::read(&mut *dev, offset, size)
+    }
+
+    fn write(&mut self, offset: u64, size: usize, value: u64) {
+        {
+            let mut dev = self.dev.borrow_mut();
+            sync_aerogpu_pci_state_into_mmio(&self.pci_cfg, &mut dev);
+            PciBarMmioHandler::write(&mut *dev, offset, size, value);
+        }
+        // A qword write spans two dword registers.
+        if Self::write_affects_display(offset)
+            || (size == 8 && Self::write_affects_display(offset + 4))
+        {
+            self.generation.bump();
+        }
+    }
+}
+
 struct AeroGpuBar1Mmio {
     dev: Rc<RefCell<AeroGpuDevice>>,
+    generation: DisplayGeneration,
 }
 
 impl PciBarMmioHandler for AeroGpuBar1Mmio {
@@ -3470,11 +4362,13 @@ impl PciBarMmioHandler for AeroGpuBar1Mmio {
 
     fn write(&mut self, offset: u64, size: usize, value: u64) {
         self.dev.borrow_mut().vram_write(offset, size, value);
+        self.generation.bump();
     }
 }
 
 struct AeroGpuLegacyVgaMmio {
     dev: Rc<RefCell<AeroGpuDevice>>,
+    generation: DisplayGeneration,
 }
 
 impl MmioHandler for AeroGpuLegacyVgaMmio {
@@ -3484,12 +4378,14 @@ impl MmioHandler for AeroGpuLegacyVgaMmio {
 
     fn write(&mut self, offset: u64, size: usize, value: u64) {
         self.dev.borrow_mut().legacy_vga_write(offset, size, value);
+        self.generation.bump();
     }
 }
 
 struct AeroGpuVgaPortWindow {
     dev: Rc<RefCell<AeroGpuDevice>>,
     clock: ManualClock,
+    generation: DisplayGeneration,
 }
 
 impl AeroGpuVgaPortWindow {
@@ -3513,60 +4409,39 @@ impl AeroGpuVgaPortWindow {
 }
 
 impl aero_platform::io::PortIoDevice for AeroGpuVgaPortWindow {
-    fn read(&mut self, port: u16, size: u8) -> u32 {
-        let size = match size {
-            0 => return 0,
-            1 | 2 | 4 => size as usize,
-            _ => return u32::MAX,
-        };
-
-        let mut out = 0u32;
+    // Registered with a byte-splitting policy, so every access is a single byte.
+    fn read(&mut self, port: u16, _size: u8) -> u32 {
         let mut dev = self.dev.borrow_mut();
-        for i in 0..size {
-            let p = port.wrapping_add(i as u16);
-            let b = match p {
-                // Input Status 1: reading resets the attribute controller flip-flop.
-                0x03DA | 0x03BA => {
-                    dev.attr_flip_flop = false;
-                    u32::from(self.input_status1_value())
-                }
-                _ => u32::from(dev.vga_port_read_u8(p)),
-            };
-            out |= b << (i * 8);
+        match port {
+            // Input Status 1: reading resets the attribute controller flip-flop.
+            0x03DA | 0x03BA => {
+                dev.attr_flip_flop = false;
+                u32::from(self.input_status1_value())
+            }
+            _ => u32::from(dev.vga_port_read_u8(port)),
         }
-        out
     }
 
-    fn write(&mut self, port: u16, size: u8, value: u32) {
-        let size = match size {
-            1 | 2 | 4 => size as usize,
-            _ => return,
-        };
+    fn write(&mut self, port: u16, _size: u8, value: u32) {
+        self.dev.borrow_mut().vga_port_write_u8(port, value as u8);
+        self.generation.bump();
+    }
 
-        let mut dev = self.dev.borrow_mut();
-        for i in 0..size {
-            let p = port.wrapping_add(i as u16);
-            let b = ((value >> (i * 8)) & 0xFF) as u8;
-            dev.vga_port_write_u8(p, b);
-        }
+    fn debug_name(&self) -> &'static str {
+        "aerogpu-vga"
     }
 }
 
 struct AeroGpuVbeDispiPortWindow {
     dev: Rc<RefCell<AeroGpuDevice>>,
+    generation: DisplayGeneration,
 }
 
 impl aero_platform::io::PortIoDevice for AeroGpuVbeDispiPortWindow {
-    fn read(&mut self, port: u16, size: u8) -> u32 {
-        // The Bochs VBE_DISPI interface is 16-bit register based (INDEX/DATA). Support only 16-bit
-        // accesses for now; this matches how most real-mode drivers interact with the interface.
-        if size == 0 {
-            return 0;
-        }
-        if size != 2 {
-            return u32::MAX;
-        }
-
+    // The Bochs VBE_DISPI interface is 16-bit register based (INDEX/DATA); the ports are
+    // registered with a 16-bit-only policy, which matches how most real-mode drivers interact with
+    // the interface.
+    fn read(&mut self, port: u16, _size: u8) -> u32 {
         let dev = self.dev.borrow();
         match port {
             aero_gpu_vga::VBE_DISPI_INDEX_PORT => u32::from(dev.vbe_dispi_index),
@@ -3577,11 +4452,7 @@ impl aero_platform::io::PortIoDevice for AeroGpuVbeDispiPortWindow {
         }
     }
 
-    fn write(&mut self, port: u16, size: u8, value: u32) {
-        if size != 2 {
-            return;
-        }
-
+    fn write(&mut self, port: u16, _size: u8, value: u32) {
         let value = (value & 0xFFFF) as u16;
         let mut dev = self.dev.borrow_mut();
         match port {
@@ -3592,6 +4463,11 @@ impl aero_platform::io::PortIoDevice for AeroGpuVbeDispiPortWindow {
             }
             _ => {}
         }
+        self.generation.bump();
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "aerogpu-vbe"
     }
 }
 
@@ -3620,9 +4496,14 @@ struct AeroGpuSnapshotV1 {
 // exact VRAM contents when pages are non-zero.
 const AEROGPU_SNAPSHOT_VERSION_V2: u16 = 2;
 
-fn encode_aerogpu_snapshot_v2(vram: &AeroGpuDevice, bar0: &AeroGpuMmioDevice) -> Vec<u8> {
-    // Preserve at most the default legacy VGA/VBE VRAM backing size (matches the V1 policy).
-    let vram_len = vram.vram.len().min(aero_gpu_vga::DEFAULT_VRAM_SIZE);
+fn encode_aerogpu_snapshot_v2(
+    vram: &AeroGpuDevice,
+    bar0: &AeroGpuMmioDevice,
+    vram_size: usize,
+) -> Vec<u8> {
+    // Preserve at most the configured VRAM aperture size (`vram_size`); the sparse page list keeps
+    // untouched VRAM out of the snapshot regardless of how large the aperture is.
+    let vram_len = vram.vram.len().min(vram_size);
     let vram_len_u32: u32 = vram_len.try_into().unwrap_or(u32::MAX);
 
     // Sparse encoding: store non-zero pages.
@@ -3682,11 +4563,21 @@ fn encode_aerogpu_snapshot_v2(vram: &AeroGpuDevice, bar0: &AeroGpuMmioDevice) ->
     let page_count_off = out.len();
     out.extend_from_slice(&0u32.to_le_bytes()); // patched after scanning
 
+    // Large apertures can hold more dirty VRAM than a single device entry may carry
+    // (`MAX_DEVICE_ENTRY_LEN`). Keep the lowest pages (scanout/LFB live at the start of VRAM) and
+    // leave headroom for the fixed-size trailers appended below.
+    const TRAILER_RESERVE: usize = 64 * 1024;
+    let page_budget = usize::try_from(snapshot::limits::MAX_DEVICE_ENTRY_LEN)
+        .unwrap_or(usize::MAX)
+        .saturating_sub(TRAILER_RESERVE);
     let mut page_count: u32 = 0;
     for (idx, chunk) in vram.vram[..vram_len].chunks(PAGE_SIZE).enumerate() {
         if chunk.iter().all(|&b| b == 0) {
             continue;
         }
+        if out.len() + 8 + chunk.len() > page_budget {
+            break;
+        }
         page_count = page_count.saturating_add(1);
         let idx_u32: u32 = idx.try_into().unwrap_or(u32::MAX);
         let len_u32: u32 = chunk.len().try_into().unwrap_or(u32::MAX);
@@ -3711,16 +4602,22 @@ fn encode_aerogpu_snapshot_v2(vram: &AeroGpuDevice, bar0: &AeroGpuMmioDevice) ->
     out.extend_from_slice(&regs.cursor_fb_gpa_pending_lo.to_le_bytes());
     out.extend_from_slice(&(regs.cursor_fb_gpa_lo_pending as u32).to_le_bytes());
 
-    // Optional trailing VGA DAC state (palette + PEL mask).
+    // Optional trailing VGA DAC state (palette, PEL mask and port latches).
     //
     // This is required for deterministic 8bpp VBE output when the guest programs colors via VGA
     // DAC ports (`0x3C8/0x3C9`). The BIOS VBE palette snapshot does not capture port-driven DAC
-    // updates, so store the DAC state here.
-    out.extend_from_slice(b"DACP");
-    out.push(vram.pel_mask);
-    for entry in &vram.dac_palette {
-        out.extend_from_slice(entry);
-    }
+    // updates, so store the DAC state here, using the encoding shared with `VgaDevice`.
+    //
+    // Format:
+    // - tag "VDAC"
+    // - u32 byte_len
+    // - payload bytes (`VgaDac::save_state`)
+    //
+    // Older snapshots carry the same state as `DACP` (PEL mask + palette) and `DACI` (latches).
+    out.extend_from_slice(b"VDAC");
+    let dac_state = vram.dac.save_state();
+    out.extend_from_slice(&(dac_state.len() as u32).to_le_bytes());
+    out.extend_from_slice(&dac_state);
     // Optional trailing VGA Attribute Controller state (palette mapping registers).
     //
     // The AeroGPU "legacy text scanout" path resolves text-mode colors via the VGA Attribute
@@ -3731,21 +4628,9 @@ fn encode_aerogpu_snapshot_v2(vram: &AeroGpuDevice, bar0: &AeroGpuMmioDevice) ->
     // first 32 bytes are observable/meaningful.
     out.extend_from_slice(&vram.attr_regs[..0x20]);
 
-    // Optional trailing VGA DAC latches (indices + partial RGB triplet).
-    //
-    // This preserves deterministic snapshot/restore semantics even if the VM is checkpointed
-    // mid-update (e.g. after writing `DAC_WRITE_INDEX` / one `DAC_DATA` byte but before the full
-    // RGB triplet is complete).
-    out.extend_from_slice(b"DACI");
-    out.push(vram.dac_read_index);
-    out.push(vram.dac_read_subindex);
-    out.push(vram.dac_write_index);
-    out.push(vram.dac_write_subindex);
-    out.extend_from_slice(&vram.dac_write_latch);
-
     // Optional trailing VGA Attribute Controller flip-flop state.
     //
-    // Similar to `DACI`, this keeps the `0x3C0` index/data sequencing deterministic across
+    // Like the DAC latches in `VDAC`, this keeps the `0x3C0` index/data sequencing deterministic across
     // snapshot/restore.
     out.extend_from_slice(b"ATST");
     out.push(vram.attr_index);
@@ -3801,6 +4686,23 @@ fn encode_aerogpu_snapshot_v2(vram: &AeroGpuDevice, bar0: &AeroGpuMmioDevice) ->
     out.extend_from_slice(&vram.vbe_dispi_x_offset.to_le_bytes());
     out.extend_from_slice(&vram.vbe_dispi_y_offset.to_le_bytes());
 
+    // Optional trailing MSI-X table + PBA (BAR0 `0x8000`/`0x9000`).
+    //
+    // Format:
+    // - tag "MSIX"
+    // - u32 table_len, table bytes
+    // - u32 pba_len, pba bytes
+    //
+    // The MSI-X enable/function-mask bits are part of the canonical PCI config space snapshot.
+    if let Some((table, pba)) = bar0.msix_snapshot_state() {
+        out.extend_from_slice(b"MSIX");
+        for bytes in [&table, &pba] {
+            let len_u32: u32 = bytes.len().try_into().unwrap_or(u32::MAX);
+            out.extend_from_slice(&len_u32.to_le_bytes());
+            out.extend_from_slice(bytes);
+        }
+    }
+
     out
 }
 
@@ -4227,6 +5129,17 @@ fn apply_aerogpu_snapshot_v2(
             const TOTAL_LEN: usize = 4 + 1 + PALETTE_LEN;
             return bytes.len() >= off.saturating_add(TOTAL_LEN);
         }
+        if tag == b"VDAC" {
+            let len_bytes = match bytes.get(off.saturating_add(4)..off.saturating_add(8)) {
+                Some(b) => b,
+                None => return false,
+            };
+            let byte_len =
+                u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]])
+                    as usize;
+            let payload_end = off.saturating_add(8).saturating_add(byte_len);
+            return bytes.len() >= payload_end;
+        }
         if tag == b"ATRG" {
             const TOTAL_LEN: usize = 4 + 0x20;
             return bytes.len() >= off.saturating_add(TOTAL_LEN);
@@ -4337,22 +5250,43 @@ fn apply_aerogpu_snapshot_v2(
     let mut restored_dac = false;
     let mut exec_state: Option<&[u8]> = None;
     // Optional trailing sections:
-    // - `DACP`: VGA DAC state (PEL mask + palette)
+    // - `VDAC`: VGA DAC state (`VgaDac::save_state`)
+    // - `DACP`/`DACI`: VGA DAC state written by older encoders (PEL mask + palette, latches)
     // - `ATRG`: VGA Attribute Controller palette mapping regs
     //
     // Keep these after the variable-length VRAM payload for forward compatibility: older decoders
     // stop after the page list, while newer versions can parse known tags and ignore the rest.
     //
-    // `DACP` is the first tag emitted by the v2 encoder. Treat it as a sentinel that tag parsing
-    // should begin; this prevents older snapshots (which might have no tags) from accidentally
-    // interpreting arbitrary trailing bytes (e.g. timebase fields) as a tag like `ATST`.
+    // `VDAC` (formerly `DACP`) is the first tag emitted by the v2 encoder. Treat it as a sentinel
+    // that tag parsing should begin; this prevents older snapshots (which might have no tags) from
+    // accidentally interpreting arbitrary trailing bytes (e.g. timebase fields) as a tag like
+    // `ATST`.
     let mut parsed_any_tag = false;
     while let Some(tag) = bytes.get(off..off.saturating_add(4)) {
-        if !parsed_any_tag && tag != b"DACP" {
+        if !parsed_any_tag && tag != b"VDAC" && tag != b"DACP" {
             break;
         }
         parsed_any_tag = true;
 
+        if tag == b"VDAC" {
+            if bytes.len() < off.saturating_add(8) {
+                break;
+            }
+            let len_bytes = bytes.get(off + 4..off + 8).unwrap_or(&[0u8, 0u8, 0u8, 0u8]);
+            let byte_len =
+                u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]])
+                    as usize;
+            let payload_off = off.saturating_add(8);
+            let payload_end = payload_off.saturating_add(byte_len);
+            if bytes.len() < payload_end {
+                break;
+            }
+            let payload = bytes.get(payload_off..payload_end).unwrap_or(&[]);
+            restored_dac = vram.dac.load_state(payload).is_ok();
+            off = payload_end;
+            continue;
+        }
+
         if tag == b"DACP" {
             const PALETTE_LEN: usize = 256 * 3;
             const TOTAL_LEN: usize = 4 + 1 + PALETTE_LEN;
@@ -4361,15 +5295,10 @@ fn apply_aerogpu_snapshot_v2(
             }
             let pel_mask = bytes.get(off + 4).copied().unwrap_or(0xFF);
             let pal_bytes = bytes.get((off + 5)..(off + 5 + PALETTE_LEN)).unwrap_or(&[]);
-            let mut palette = [[0u8; 3]; 256];
-            for (idx, entry) in palette.iter_mut().enumerate() {
-                let base = idx * 3;
-                if base + 2 < pal_bytes.len() {
-                    *entry = [pal_bytes[base], pal_bytes[base + 1], pal_bytes[base + 2]];
-                }
+            vram.dac.set_pel_mask(pel_mask);
+            for (idx, rgb) in pal_bytes.chunks_exact(3).enumerate() {
+                vram.dac.set_entry(idx as u8, [rgb[0], rgb[1], rgb[2]]);
             }
-            vram.pel_mask = pel_mask;
-            vram.dac_palette = palette;
             restored_dac = true;
             off = off.saturating_add(TOTAL_LEN);
             continue;
@@ -4395,11 +5324,13 @@ fn apply_aerogpu_snapshot_v2(
                 break;
             }
             if let Some(payload) = bytes.get((off + 4)..(off + 4 + PAYLOAD_LEN)) {
-                vram.dac_read_index = payload[0];
-                vram.dac_read_subindex = payload[1];
-                vram.dac_write_index = payload[2];
-                vram.dac_write_subindex = payload[3];
-                vram.dac_write_latch.copy_from_slice(&payload[4..7]);
+                vram.dac.restore_indices(
+                    payload[0],
+                    payload[1],
+                    payload[2],
+                    payload[3],
+                    [payload[4], payload[5], payload[6]],
+                );
             }
             off = off.saturating_add(TOTAL_LEN);
             continue;
@@ -4516,6 +5447,23 @@ fn apply_aerogpu_snapshot_v2(
                 vram.vbe_dispi_x_offset = read_u16();
                 vram.vbe_dispi_y_offset = read_u16();
             }
+            off = off.saturating_add(TOTAL_LEN);
+        }
+    }
+    // Optional trailing MSI-X table + PBA (follows `BVBE`).
+    let mut msix_state: Option<(&[u8], &[u8])> = None;
+    if bytes.get(off..off.saturating_add(4)) == Some(b"MSIX".as_slice()) {
+        fn read_blob<'a>(bytes: &'a [u8], off: &mut usize) -> Option<&'a [u8]> {
+            let len = read_u32(bytes, off)? as usize;
+            let end = off.checked_add(len)?;
+            let blob = bytes.get(*off..end)?;
+            *off = end;
+            Some(blob)
+        }
+
+        off += 4;
+        if let Some(table) = read_blob(bytes, &mut off) {
+            msix_state = read_blob(bytes, &mut off).map(|pba| (table, pba));
         }
     }
 
@@ -4568,10 +5516,35 @@ fn apply_aerogpu_snapshot_v2(
             return None;
         }
     }
+    if let Some((table, pba)) = msix_state {
+        if bar0.restore_msix_state(table, pba).is_err() {
+            return None;
+        }
+    }
 
     Some(restored_dac)
 }
 
+struct TpmCrbMmio {
+    tpm: Rc<RefCell<TpmCrb>>,
+}
+
+impl MmioHandler for TpmCrbMmio {
+    fn read(&mut self, offset: u64, size: usize) -> u64 {
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return 0;
+        }
+        self.tpm.borrow_mut().mmio_read(offset, size)
+    }
+
+    fn write(&mut self, offset: u64, size: usize, value: u64) {
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return;
+        }
+        self.tpm.borrow_mut().mmio_write(offset, size, value);
+    }
+}
+
 // -----------------------------------------------------------------------------
 // PC platform MMIO adapters (LAPIC / IOAPIC / HPET)
 // -----------------------------------------------------------------------------
@@ -4580,20 +5553,15 @@ struct HpetMmio {
     interrupts: Rc<RefCell<PlatformInterrupts>>,
 }
 
+// Mapped with a 4/8-byte natural-alignment policy, so only well-formed accesses reach the HPET.
 impl MmioHandler for HpetMmio {
     fn read(&mut self, offset: u64, size: usize) -> u64 {
-        if !matches!(size, 1 | 2 | 4 | 8) {
-            return 0;
-        }
         let mut hpet = self.hpet.borrow_mut();
         let mut interrupts = self.interrupts.borrow_mut();
         hpet.mmio_read(offset, size, &mut *interrupts)
     }
 
     fn write(&mut self, offset: u64, size: usize, value: u64) {
-        if !matches!(size, 1 | 2 | 4 | 8) {
-            return;
-        }
         let mut hpet = self.hpet.borrow_mut();
         let mut interrupts = self.interrupts.borrow_mut();
         hpet.mmio_write(offset, size, value, &mut *interrupts);
@@ -4639,6 +5607,10 @@ impl aero_platform::io::PortIoDevice for IdePort {
         self.sync_config();
         self.ide.borrow_mut().io_write(port, size, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "piix3-ide"
+    }
 }
 
 /// Bus Master IDE (BAR4) handler registered via the machine's PCI I/O window.
@@ -4772,6 +5744,9 @@ impl<T: 'static> core::ops::Deref for SharedStateHandle<T> {
 
 struct PciIoBarWindow {
     router: SharedPciIoBarRouter,
+    // The window claims the whole PCI I/O range, so ports no BAR decodes are reported to the
+    // unknown-access policy here rather than by the port I/O bus.
+    unknown_access: Rc<unknown_access::UnknownAccessTracker>,
 }
 
 impl PciIoBarWindow {
@@ -4793,11 +5768,19 @@ impl aero_platform::io::PortIoDevice for PciIoBarWindow {
             1 | 2 | 4 => size as usize,
             _ => return mask,
         };
-        self.router
-            .borrow_mut()
-            .dispatch_read(port, size_usize)
-            .map(|v| v & mask)
-            .unwrap_or(mask)
+        let value = self.router.borrow_mut().dispatch_read(port, size_usize);
+        match value {
+            Some(v) => v & mask,
+            None => {
+                self.unknown_access.record(
+                    UnknownAccessSpace::PortIo,
+                    u64::from(port),
+                    u32::from(size),
+                    false,
+                );
+                mask
+            }
+        }
     }
 
     fn write(&mut self, port: u16, size: u8, value: u32) {
@@ -4805,10 +5788,22 @@ impl aero_platform::io::PortIoDevice for PciIoBarWindow {
             1 | 2 | 4 => size as usize,
             _ => return,
         };
-        let _ = self
+        let claimed = self
             .router
             .borrow_mut()
             .dispatch_write(port, size_usize, value);
+        if !claimed {
+            self.unknown_access.record(
+                UnknownAccessSpace::PortIo,
+                u64::from(port),
+                u32::from(size),
+                true,
+            );
+        }
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "pci-io-bar-window"
     }
 }
 
@@ -4900,6 +5895,14 @@ pub struct Machine {
     cfg: MachineConfig,
     chipset: ChipsetState,
     reset_latch: ResetLatch,
+    // Source of the request in `reset_latch`, recorded by the devices' `SourcedResetSink`s.
+    reset_source: PendingResetSource,
+    // Reset reported by `run_slice` that the host has not acted on yet.
+    pending_reset: Option<(ResetKind, ResetSource)>,
+    // Sleep request latched by the ACPI PM `request_sleep` callback (`PM1a_CNT.SLP_EN` write) and
+    // folded into `power_state` at the next `run_slice` exit check.
+    acpi_sleep_latch: Rc<Cell<Option<AcpiSleepState>>>,
+    power_state: GuestPowerState,
 
     cpu: CpuCore,
     ap_cpus: Vec<CpuCore>,
@@ -4912,6 +5915,58 @@ pub struct Machine {
     display_fb: Vec<u32>,
     display_width: u32,
     display_height: u32,
+    // Damage counter bumped by guest writes to display devices (see `display_generation`), and the
+    // generation `display_fb` was last rendered at. `None` forces the next present to re-render.
+    display_generation: DisplayGeneration,
+    display_presented_generation: Option<u64>,
+    // Set by the render paths when the presented frame depends on state the generation does not
+    // track (guest RAM scanouts, host-side backend output).
+    display_present_untracked: bool,
+    // Blink epoch of the last AeroGPU legacy text frame, if it shows blinking text or a cursor.
+    display_text_blink_epoch: Option<u64>,
+    // Optional display recorder (see `start_display_recording`). Host configuration, not guest
+    // state.
+    display_recorder: Option<display_recording::DisplayRecorder>,
+
+    // Host-visible performance counters (see `perf_counters`). Not guest state: never
+    // snapshotted, and preserved across reset/restore.
+    perf: MachinePerfCounters,
+    mmio_perf: perf::MmioPerfCounters,
+    // Device tick scheduling (see `set_device_tick_scheduling`). Host configuration, not guest
+    // state.
+    tick_scheduler: TickScheduler,
+    // Optional guest hang watchdog (see `set_hang_watchdog`). Host configuration, not guest state.
+    hang_watchdog: Option<watchdog::HangWatchdog>,
+    // Optional dirty-page rate sampler (see `start_dirty_sampling`). Host configuration, not guest
+    // state.
+    dirty_sampler: Option<dirty_sampling::DirtySampler>,
+    // Optional input latency sampler (see `start_input_latency_sampling`). Host configuration, not
+    // guest state.
+    input_latency: Option<input_latency::InputLatencyTracker>,
+    // Reports handed to the synthetic USB HID devices; lets input latency sampling detect USB
+    // deliveries.
+    usb_input_deliveries: u64,
+    /// Periodic timer catch-up policy (see [`Machine::set_timer_catchup_policy`]).
+    timer_catchup: Option<TimerCatchupPolicy>,
+    // Storage latency/fault injection (see `set_storage_fault_policy`). Host configuration, not
+    // guest state.
+    storage_fault_policy: Option<StorageFaultPolicy>,
+    // Unknown port/MMIO access policy and log (see `set_unknown_access_config`). Host
+    // configuration, not guest state.
+    unknown_access: Rc<unknown_access::UnknownAccessTracker>,
+    // ROM-write monitor and SMC detector (see `set_rom_write_monitor`). Host diagnostics, not
+    // snapshotted; survive reset.
+    code_diagnostics: Rc<code_diagnostics::CodeDiagnostics>,
+    // Unified event stream (see `take_events`). Host telemetry, not snapshotted; survives reset.
+    events: Rc<machine_events::MachineEventLog>,
+    // Port 0x80 POST codes (see `post_code_history`). Host telemetry, not snapshotted; cleared on
+    // reset.
+    post_codes: Rc<post_code::PostCodeLog>,
+    // Host-injected NMIs/exceptions/SMIs (see `inject_nmi`). Host test state, not snapshotted.
+    event_injector: event_injection::EventInjector,
+    // Boot progress derived from architectural events (see `boot_stage`). Host telemetry, not
+    // snapshotted; cleared when firmware POST reruns.
+    boot_stage: boot_stage::BootStageTracker,
 
     // Optional shared scanout descriptor used by the browser presentation pipeline.
     //
@@ -4921,6 +5976,11 @@ pub struct Machine {
     #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
     scanout_state: Option<SharedStateHandle<ScanoutState>>,
 
+    // Optional host region receiving presented frames through `scanout_state` (see
+    // `Machine::set_scanout_frame_slots`).
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+    scanout_frame_slots: Option<ScanoutFrameSlots>,
+
     // Optional shared hardware cursor descriptor used by the browser presentation pipeline.
     //
     // This is only available when publishing into an external shared header is supported: native
@@ -4944,6 +6004,7 @@ pub struct Machine {
     ahci_port0_overlay: Option<snapshot::DiskOverlayRef>,
     ide_secondary_master_atapi_overlay: Option<snapshot::DiskOverlayRef>,
     ide_primary_master_overlay: Option<snapshot::DiskOverlayRef>,
+    floppy_overlay: Option<snapshot::DiskOverlayRef>,
     restored_disk_overlays: Option<snapshot::DiskOverlayRefs>,
 
     // Optional PC platform devices. These are behind `Rc<RefCell<_>>` so their host wiring
@@ -4953,6 +6014,11 @@ pub struct Machine {
     interrupts: Option<Rc<RefCell<PlatformInterrupts>>>,
     pit: Option<SharedPit8254>,
     rtc: Option<SharedRtcCmos<ManualClock, PlatformIrqLine>>,
+    // Persisted CMOS RAM bank (`initial_nvram` / `import_nvram`) that every cold reset starts
+    // from.
+    pending_cmos_nvram: Option<[u8; CMOS_NVRAM_LEN]>,
+    dma: Option<Rc<RefCell<Dma8237>>>,
+    fdc: Option<SharedFloppyController>,
     pci_cfg: Option<SharedPciConfigPorts>,
     pci_intx: Option<Rc<RefCell<PciIntxRouter>>>,
     acpi_pm: Option<SharedAcpiPmIo<ManualClock>>,
@@ -4962,8 +6028,15 @@ pub struct Machine {
     virtio_input_keyboard: Option<Rc<RefCell<VirtioPciDevice>>>,
     virtio_input_mouse: Option<Rc<RefCell<VirtioPciDevice>>>,
     virtio_input_tablet: Option<Rc<RefCell<VirtioPciDevice>>>,
+    virtio_balloon: Option<Rc<RefCell<VirtioPciDevice>>>,
+    virtio_9p: Option<Rc<RefCell<VirtioPciDevice>>>,
+    virtio_rng: Option<Rc<RefCell<VirtioPciDevice>>>,
+    tpm: Option<Rc<RefCell<TpmCrb>>>,
     vga: Option<Rc<RefCell<VgaDevice>>>,
     aerogpu: Option<Rc<RefCell<AeroGpuDevice>>>,
+    /// Bumped whenever the AeroGPU VRAM backing is reallocated, dropped, cleared or restored, so
+    /// host views handed out by [`Machine::aerogpu_vram_export`] can detect that they are stale.
+    aerogpu_vram_generation: u64,
     aerogpu_mmio: Option<Rc<RefCell<AeroGpuMmioDevice>>>,
     ahci: Option<Rc<RefCell<AhciPciDevice>>>,
     nvme: Option<Rc<RefCell<NvmePciDevice>>>,
@@ -4972,7 +6045,15 @@ pub struct Machine {
     uhci: Option<Rc<RefCell<UhciPciDevice>>>,
     ehci: Option<Rc<RefCell<EhciPciDevice>>>,
     xhci: Option<Rc<RefCell<XhciPciDevice>>>,
-    /// Optional synthetic USB HID devices behind an external hub on UHCI root port 0.
+    /// Shared USB2 physical port mux backing the first UHCI/EHCI root ports when both controllers
+    /// are enabled.
+    usb2_mux: Option<Rc<RefCell<Usb2PortMux>>>,
+    /// Host-proxied USB devices attached via [`Machine::usb_proxy_attach`], keyed by handle.
+    usb_proxies: HashMap<u32, MachineUsbProxy>,
+    next_usb_proxy_id: u32,
+    /// xHCI root ports populated via [`Machine::xhci_attach_device`].
+    xhci_attached_ports: BTreeSet<u8>,
+    /// Optional synthetic USB HID devices behind an external hub on UHCI (or xHCI) root port 0.
     usb_hid_keyboard: Option<UsbHidKeyboardHandle>,
     usb_hid_mouse: Option<UsbHidMouseHandle>,
     usb_hid_gamepad: Option<UsbHidGamepadHandle>,
@@ -4986,6 +6067,10 @@ pub struct Machine {
     xhci_ns_remainder: u64,
     bios: Bios,
     disk: SharedDisk,
+    /// Optional 1.44MB floppy image for drive A:, shared between the FDC and BIOS INT 13h.
+    floppy: Option<SharedDisk>,
+    /// One-shot request to show the firmware boot menu on the next reset.
+    boot_menu_requested: bool,
     install_media: Option<InstallMedia>,
     /// Host-selected BIOS boot drive number exposed in `DL` when transferring control to the boot
     /// sector.
@@ -5014,11 +6099,24 @@ pub struct Machine {
     virtio_blk_auto_attach_shared_disk: bool,
     network_backend: Option<Box<dyn NetworkBackend>>,
 
-    serial: Option<SharedSerial16550>,
+    /// COM1..COM4 UARTs (see [`serial_ports`]).
+    serial: [Option<SharedSerial16550>; SERIAL_PORT_COUNT],
+    /// One wired-OR line per ISA IRQ used by an enabled COM port.
+    serial_irq_lines: Vec<(u8, PlatformIrqLine)>,
+    serial_channels: [Option<SerialChannel>; SERIAL_PORT_COUNT],
+    kd_bridge: Option<kd_bridge::KdBridge>,
     i8042: Option<SharedI8042Controller>,
-    serial_log: Vec<u8>,
+    /// Accumulated output per COM port (bytes routed to a [`SerialChannel`] are not logged).
+    serial_logs: [Vec<u8>; SERIAL_PORT_COUNT],
+    /// Bytes retained per output log (`None` = unbounded).
+    serial_log_capacity: Option<usize>,
+    /// Host-side output fan-out; not snapshotted.
+    serial_sinks: serial_ports::SerialSinks,
     debugcon_log: SharedDebugConLog,
     ps2_mouse_buttons: u8,
+    // Host-side pointer frames not yet handed to the devices (see `pointer_coalesce`).
+    virtio_mouse_backlog: pointer_coalesce::PointerBacklog<u16>,
+    ps2_mouse_backlog: pointer_coalesce::PointerBacklog<Ps2MouseButton>,
     // Tracks which backend delivered the most recent press for each Consumer Control usage so
     // releases are routed consistently even if virtio-input becomes ready mid-hold.
     //
@@ -5057,12 +6155,23 @@ pub struct Machine {
     // - 1: synthetic USB HID mouse
     // - 2: virtio-input mouse
     input_batch_mouse_backend: u8,
+    // Host input policy (see `Machine::set_input_focus` / `Machine::set_keyboard_grab`).
+    //
+    // Like the rest of the host-side input bookkeeping above, this is not part of the snapshot
+    // format and survives `Machine::reset`.
+    input_focus_head: u8,
+    keyboard_grab: bool,
+    keyboard_grab_filter: Vec<u8>,
+    // Host-side NIC cable state (see `Machine::set_nic_link_up`); not snapshotted, survives reset.
+    nic_link_up: bool,
 
     next_snapshot_id: u64,
     last_snapshot_id: Option<u64>,
     /// Deterministic guest time accumulator used when converting CPU cycles (TSC ticks) into
     /// nanoseconds for platform device ticking.
     guest_time: GuestTime,
+    /// Synthetic aero-bench device (see [`MachineConfig::enable_aero_bench`]).
+    bench: Option<BenchDevice>,
 
     /// Deferred snapshot restore error surfaced via `SnapshotTarget::post_restore`.
     ///
@@ -5072,6 +6181,106 @@ pub struct Machine {
     restore_error: Option<snapshot::SnapshotError>,
 }
 
+/// The machine's synthetic USB HID handles, borrowed so they can be plugged into an external hub
+/// on either UHCI or xHCI.
+struct SyntheticUsbHidHandles<'a> {
+    keyboard: &'a mut Option<UsbHidKeyboardHandle>,
+    mouse: &'a mut Option<UsbHidMouseHandle>,
+    gamepad: &'a mut Option<UsbHidGamepadHandle>,
+    consumer_control: &'a mut Option<UsbHidConsumerControlHandle>,
+}
+
+impl SyntheticUsbHidHandles<'_> {
+    /// Attach each synthetic device to its canonical hub port, skipping ports that are occupied
+    /// or beyond `port_count`. Handles are created on first use and reused afterwards.
+    fn attach_to_hub(self, hub: &mut dyn aero_usb::UsbDeviceModel, port_count: u8) {
+        if port_count >= Machine::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT
+            && hub
+                .hub_port_device_mut(Machine::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT)
+                .is_err()
+        {
+            let keyboard = self
+                .keyboard
+                .get_or_insert_with(UsbHidKeyboardHandle::new)
+                .clone();
+            let _ = hub.hub_attach_device(
+                Machine::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT,
+                Box::new(keyboard),
+            );
+        }
+        if port_count >= Machine::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT
+            && hub
+                .hub_port_device_mut(Machine::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT)
+                .is_err()
+        {
+            let mouse = self
+                .mouse
+                .get_or_insert_with(UsbHidMouseHandle::new)
+                .clone();
+            let _ =
+                hub.hub_attach_device(Machine::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT, Box::new(mouse));
+        }
+        if port_count >= Machine::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT
+            && hub
+                .hub_port_device_mut(Machine::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT)
+                .is_err()
+        {
+            let gamepad = self
+                .gamepad
+                .get_or_insert_with(UsbHidGamepadHandle::new)
+                .clone();
+            let _ = hub.hub_attach_device(
+                Machine::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT,
+                Box::new(gamepad),
+            );
+        }
+        if port_count >= Machine::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT
+            && hub
+                .hub_port_device_mut(Machine::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT)
+                .is_err()
+        {
+            let consumer = self
+                .consumer_control
+                .get_or_insert_with(UsbHidConsumerControlHandle::new)
+                .clone();
+            let _ = hub.hub_attach_device(
+                Machine::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT,
+                Box::new(consumer),
+            );
+        }
+    }
+}
+
+/// Opaque handle for a host-proxied USB device attached with [`Machine::usb_proxy_attach`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProxyHandle(pub u32);
+
+#[derive(Debug, Clone)]
+struct MachineUsbProxy {
+    port: u8,
+    device: ProxyUsbDevice,
+}
+
+/// Host network backend lent to the firmware's polled PXE stack for the duration of POST.
+struct PxeBackendNic {
+    mac: [u8; 6],
+    backend: Box<dyn NetworkBackend>,
+}
+
+impl firmware::bios::PxeNic for PxeBackendNic {
+    fn mac_addr(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    fn transmit(&mut self, frame: &[u8]) {
+        self.backend.transmit(frame.to_vec());
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.backend.poll_receive()
+    }
+}
+
 /// Active scanout source category selected by the machine's scanout handoff policy.
 ///
 /// This primarily reflects *guest-programmed* scanout ownership (legacy VGA/VBE vs BAR0 scanout0
@@ -5119,6 +6328,8 @@ impl Machine {
     pub const DISK_ID_INSTALL_MEDIA: u32 = 1;
     /// `disk_id=2`: Optional IDE primary master ATA disk (if exposed as a separately managed disk).
     pub const DISK_ID_IDE_PRIMARY_MASTER: u32 = 2;
+    /// `disk_id=3`: Optional 1.44MB floppy image (82077AA FDC drive A:).
+    pub const DISK_ID_FLOPPY: u32 = 3;
 
     // ---------------------------------------------------------------------
     // UHCI synthetic HID topology constants (normative)
@@ -5131,6 +6342,8 @@ impl Machine {
     /// UHCI root port index reserved for the external hub (synthetic HID + WebHID passthrough).
     pub const UHCI_EXTERNAL_HUB_ROOT_PORT: u8 = 0;
     /// UHCI root port index reserved for the guest-visible WebUSB passthrough device.
+    ///
+    /// This is also a physical USB 2.0 port index (see [`Machine::usb_attach_webusb_passthrough`]).
     pub const UHCI_WEBUSB_ROOT_PORT: u8 = 1;
     /// Default downstream port count for the external hub on [`Self::UHCI_EXTERNAL_HUB_ROOT_PORT`].
     pub const UHCI_EXTERNAL_HUB_PORT_COUNT: u8 = 16;
@@ -5149,16 +6362,121 @@ impl Machine {
     pub const UHCI_EXTERNAL_HUB_FIRST_DYNAMIC_PORT: u8 =
         Self::UHCI_SYNTHETIC_HID_HUB_PORT_COUNT + 1;
 
+    // ---------------------------------------------------------------------
+    // xHCI root hub layout
+    // ---------------------------------------------------------------------
+
+    /// Number of USB2 root ports on the xHCI controller (0-based indices `0..8`).
+    pub const XHCI_USB2_PORT_COUNT: u8 = 8;
+    /// Number of USB3 root ports on the xHCI controller; they follow the USB2 ports.
+    pub const XHCI_USB3_PORT_COUNT: u8 = 4;
+    /// First xHCI USB3 root port index.
+    pub const XHCI_FIRST_USB3_PORT: u8 = Self::XHCI_USB2_PORT_COUNT;
+    /// xHCI root port index used for the external hub when
+    /// [`MachineConfig::synthetic_usb_hid_on_xhci`] is set. Hub port numbers match the UHCI
+    /// `UHCI_SYNTHETIC_HID_*_HUB_PORT` constants.
+    pub const XHCI_EXTERNAL_HUB_ROOT_PORT: u8 = 0;
+    /// Downstream port count for the xHCI external hub (Route Strings address hub ports 1..=15).
+    pub const XHCI_EXTERNAL_HUB_PORT_COUNT: u8 = 15;
+
+    fn validate_numa_nodes(
+        cfg: &MachineConfig,
+        nodes: &[NumaNodeConfig],
+    ) -> Result<(), MachineError> {
+        const NODE_MEM_ALIGN: u64 = 1024 * 1024;
+        let invalid = |msg: String| Err(MachineError::InvalidNumaTopology(msg));
+
+        if !(cfg.enable_pc_platform && cfg.enable_acpi) {
+            return invalid("requires enable_pc_platform=true and enable_acpi=true".to_string());
+        }
+        if nodes.is_empty() {
+            return invalid("at least one node is required".to_string());
+        }
+        let mut cpu_node = vec![None; usize::from(cfg.cpu_count)];
+        let mut total_mem = 0u64;
+        for (node_idx, node) in nodes.iter().enumerate() {
+            for &cpu in &node.cpus {
+                match cpu_node.get_mut(usize::from(cpu)) {
+                    None => {
+                        return invalid(format!(
+                            "node {node_idx} lists cpu {cpu} but cpu_count={}",
+                            cfg.cpu_count
+                        ));
+                    }
+                    Some(Some(other)) => {
+                        return invalid(format!(
+                            "cpu {cpu} is assigned to both node {other} and node {node_idx}"
+                        ));
+                    }
+                    Some(slot) => *slot = Some(node_idx),
+                }
+            }
+            if node.mem_bytes == 0 || !node.mem_bytes.is_multiple_of(NODE_MEM_ALIGN) {
+                return invalid(format!(
+                    "node {node_idx} mem_bytes={} must be a non-zero multiple of 1MiB",
+                    node.mem_bytes
+                ));
+            }
+            total_mem = total_mem.saturating_add(node.mem_bytes);
+        }
+        if let Some(cpu) = cpu_node.iter().position(Option::is_none) {
+            return invalid(format!("cpu {cpu} is not assigned to any node"));
+        }
+        if total_mem != cfg.ram_size_bytes {
+            return invalid(format!(
+                "node memory sums to {total_mem} bytes but ram_size_bytes={}",
+                cfg.ram_size_bytes
+            ));
+        }
+        Ok(())
+    }
+
     fn validate_cfg(cfg: &MachineConfig) -> Result<(), MachineError> {
         if cfg.cpu_count == 0 {
             return Err(MachineError::InvalidCpuCount(cfg.cpu_count));
         }
+        if let Some(nodes) = &cfg.numa_nodes {
+            Self::validate_numa_nodes(cfg, nodes)?;
+        }
         if cfg.enable_e1000 && cfg.enable_virtio_net {
             return Err(MachineError::MultipleNicsEnabled);
         }
+        if let Some(blob) = &cfg.initial_nvram {
+            nvram::NvramImage::decode(blob).map_err(MachineError::InvalidNvram)?;
+        }
         if cfg.enable_virtio_input_tablet && !cfg.enable_virtio_input {
             return Err(MachineError::VirtioInputTabletRequiresVirtioInput);
         }
+        if let Some(size) = cfg.aerogpu_vram_size_bytes {
+            if !size.is_power_of_two()
+                || !(AEROGPU_VRAM_SIZE_MIN..=AEROGPU_VRAM_SIZE_MAX).contains(&size)
+            {
+                return Err(MachineError::InvalidAeroGpuVramSize(size));
+            }
+        }
+        if let Some(count) = cfg.virtio_blk_num_queues {
+            if !(1..=VIRTIO_BLK_MAX_QUEUES).contains(&count) {
+                return Err(MachineError::InvalidVirtioBlkQueueCount(count));
+            }
+        }
+        for port in 0..SERIAL_PORT_COUNT {
+            let irq = cfg.serial_irqs[port];
+            if Self::serial_port_enabled(cfg, port) && (irq == 0 || irq == 2 || irq >= 16) {
+                return Err(MachineError::InvalidSerialIrq { port, irq });
+            }
+        }
+        let num_gsis = if cfg.enable_second_ioapic {
+            2 * SECOND_IOAPIC_GSI_BASE
+        } else {
+            SECOND_IOAPIC_GSI_BASE
+        };
+        for (pirq, &gsi) in cfg.pci_pirq_to_gsi.iter().enumerate() {
+            let isa_routable = u8::try_from(gsi)
+                .is_ok_and(|irq| aero_devices::pci::piix3_pirq_route_to_gsi(irq) == Some(gsi));
+            if !(isa_routable || (16..num_gsis).contains(&gsi)) {
+                return Err(MachineError::InvalidPciPirqGsi { pirq, gsi });
+            }
+        }
         if cfg.enable_aerogpu {
             if !cfg.enable_pc_platform {
                 return Err(MachineError::AeroGpuRequiresPcPlatform);
@@ -5206,8 +6524,23 @@ impl Machine {
             }
             return Err(MachineError::VirtioBlkRequiresPcPlatform);
         }
-        if cfg.enable_synthetic_usb_hid && !cfg.enable_uhci {
-            return Err(MachineError::SyntheticUsbHidRequiresUhci);
+        if cfg.enable_virtio_balloon && !cfg.enable_pc_platform {
+            return Err(MachineError::VirtioBalloonRequiresPcPlatform);
+        }
+        if cfg.enable_virtio_9p && !cfg.enable_pc_platform {
+            return Err(MachineError::Virtio9pRequiresPcPlatform);
+        }
+        if cfg.enable_virtio_rng && !cfg.enable_pc_platform {
+            return Err(MachineError::VirtioRngRequiresPcPlatform);
+        }
+        if cfg.enable_synthetic_usb_hid {
+            if cfg.synthetic_usb_hid_on_xhci {
+                if !cfg.enable_xhci {
+                    return Err(MachineError::SyntheticUsbHidRequiresXhci);
+                }
+            } else if !cfg.enable_uhci {
+                return Err(MachineError::SyntheticUsbHidRequiresUhci);
+            }
         }
         if cfg.enable_uhci && !cfg.enable_pc_platform {
             return Err(MachineError::UhciRequiresPcPlatform);
@@ -5229,10 +6562,24 @@ impl Machine {
 
     fn build(cfg: MachineConfig, chipset: ChipsetState, mem: SystemMemory) -> Self {
         let boot_drive = cfg.boot_drive;
+        let boot_order = cfg.boot_order.clone();
+        let smbios = cfg.smbios.clone();
+        let cpu_count = cfg.cpu_count;
+        let events = Rc::new(machine_events::MachineEventLog::default());
+        // `validate_cfg` already rejected malformed blobs.
+        let pending_cmos_nvram = cfg
+            .initial_nvram
+            .as_deref()
+            .and_then(|blob| nvram::NvramImage::decode(blob).ok())
+            .and_then(|image| image.rtc_cmos);
         Self {
             cfg,
             chipset,
             reset_latch: ResetLatch::new(),
+            reset_source: Rc::new(RefCell::new(None)),
+            pending_reset: None,
+            acpi_sleep_latch: Rc::new(Cell::new(None)),
+            power_state: GuestPowerState::Running,
             cpu: CpuCore::new(CpuMode::Real),
             ap_cpus: Vec::new(),
             assist: AssistContext::default(),
@@ -5242,18 +6589,46 @@ impl Machine {
             display_fb: Vec::new(),
             display_width: 0,
             display_height: 0,
+            display_generation: DisplayGeneration::default(),
+            display_presented_generation: None,
+            display_present_untracked: false,
+            display_text_blink_epoch: None,
+            display_recorder: None,
+            perf: MachinePerfCounters::new(usize::from(cpu_count)),
+            mmio_perf: perf::MmioPerfCounters::default(),
+            tick_scheduler: TickScheduler::default(),
+            hang_watchdog: None,
+            dirty_sampler: None,
+            input_latency: None,
+            usb_input_deliveries: 0,
+            timer_catchup: Some(TimerCatchupPolicy::default()),
+            storage_fault_policy: None,
+            unknown_access: Rc::new(unknown_access::UnknownAccessTracker::with_events(
+                events.clone(),
+            )),
+            code_diagnostics: Rc::default(),
+            events,
+            post_codes: Rc::default(),
+            event_injector: event_injection::EventInjector::new(usize::from(cpu_count)),
+            boot_stage: boot_stage::BootStageTracker::default(),
             #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
             scanout_state: None,
             #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+            scanout_frame_slots: None,
+            #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
             cursor_state: None,
             ahci_port0_overlay: None,
             ide_secondary_master_atapi_overlay: None,
             ide_primary_master_overlay: None,
+            floppy_overlay: None,
             restored_disk_overlays: None,
             platform_clock: None,
             interrupts: None,
             pit: None,
             rtc: None,
+            pending_cmos_nvram,
+            dma: None,
+            fdc: None,
             pci_cfg: None,
             pci_intx: None,
             acpi_pm: None,
@@ -5263,8 +6638,13 @@ impl Machine {
             virtio_input_keyboard: None,
             virtio_input_mouse: None,
             virtio_input_tablet: None,
+            virtio_balloon: None,
+            virtio_9p: None,
+            virtio_rng: None,
+            tpm: None,
             vga: None,
             aerogpu: None,
+            aerogpu_vram_generation: 0,
             aerogpu_mmio: None,
             ahci: None,
             nvme: None,
@@ -5277,6 +6657,10 @@ impl Machine {
             usb_hid_consumer_control: None,
             ehci: None,
             xhci: None,
+            usb2_mux: None,
+            usb_proxies: HashMap::new(),
+            next_usb_proxy_id: 1,
+            xhci_attached_ports: BTreeSet::new(),
             ide_irq14_line: None,
             ide_irq15_line: None,
             uhci_ns_remainder: 0,
@@ -5284,28 +6668,44 @@ impl Machine {
             xhci_ns_remainder: 0,
             bios: Bios::new(BiosConfig {
                 boot_drive,
+                boot_order,
+                smbios,
                 ..Default::default()
             }),
             disk: SharedDisk::from_bytes(Vec::new()).expect("empty disk is valid"),
+            floppy: None,
+            boot_menu_requested: false,
             install_media: None,
             boot_drive,
             ahci_port0_auto_attach_shared_disk: true,
             virtio_blk_auto_attach_shared_disk: true,
             network_backend: None,
-            serial: None,
+            serial: Default::default(),
+            serial_irq_lines: Vec::new(),
+            serial_channels: Default::default(),
+            kd_bridge: None,
             i8042: None,
-            serial_log: Vec::new(),
+            serial_logs: Default::default(),
+            serial_log_capacity: None,
+            serial_sinks: Default::default(),
             debugcon_log: Rc::new(RefCell::new(Vec::new())),
             ps2_mouse_buttons: 0,
+            virtio_mouse_backlog: Default::default(),
+            ps2_mouse_backlog: Default::default(),
             consumer_usage_backend: [0u8; 0x0400],
             input_batch_pressed_keyboard_usages: [0u8; 256],
             input_batch_pressed_keyboard_usage_count: 0,
             input_batch_keyboard_backend: 0,
             input_batch_mouse_buttons_mask: 0,
             input_batch_mouse_backend: 0,
+            input_focus_head: 0,
+            keyboard_grab: true,
+            keyboard_grab_filter: DEFAULT_KEYBOARD_GRAB_FILTER.to_vec(),
+            nic_link_up: true,
             next_snapshot_id: 1,
             last_snapshot_id: None,
             guest_time: GuestTime::default(),
+            bench: None,
             restore_error: None,
         }
     }
@@ -5468,17 +6868,42 @@ impl Machine {
         // address (0xFEE0_0000). Do not map it into the shared `SystemMemory` bus; CPU execution
         // wraps `SystemMemory` in a per-vCPU adapter that routes LAPIC accesses to the currently
         // running vCPU's LAPIC instance.
+        let mmio_perf = self.mmio_perf.clone();
         self.mem
-            .map_mmio_once(IOAPIC_MMIO_BASE, IOAPIC_MMIO_SIZE, || {
-                Box::new(IoApicMmio::from_platform_interrupts(interrupts.clone()))
-            });
-        self.mem
-            .map_mmio_once(hpet::HPET_MMIO_BASE, hpet::HPET_MMIO_SIZE, || {
-                Box::new(HpetMmio {
-                    hpet: hpet.clone(),
-                    interrupts: interrupts.clone(),
+            .map_mmio_once("ioapic", IOAPIC_MMIO_BASE, IOAPIC_MMIO_SIZE, || {
+                Box::new(perf::CountedMmio {
+                    inner: IoApicMmio::from_platform_interrupts(interrupts.clone()),
+                    counter: mmio_perf.ioapic.clone(),
                 })
             });
+        if self.cfg.enable_second_ioapic {
+            self.mem
+                .map_mmio_once("ioapic2", SECOND_IOAPIC_MMIO_BASE, IOAPIC_MMIO_SIZE, || {
+                    Box::new(perf::CountedMmio {
+                        inner: IoApicMmio::from_platform_interrupts_for_ioapic(
+                            interrupts.clone(),
+                            1,
+                        ),
+                        counter: mmio_perf.ioapic.clone(),
+                    })
+                });
+        }
+        // The HPET register file only supports naturally aligned 32/64-bit accesses.
+        self.mem.map_mmio_once_with_policy(
+            "hpet",
+            hpet::HPET_MMIO_BASE,
+            hpet::HPET_MMIO_SIZE,
+            memory::AccessPolicy::Natural(memory::AccessSizes::DWORD | memory::AccessSizes::QWORD),
+            || {
+                Box::new(perf::CountedMmio {
+                    inner: HpetMmio {
+                        hpet: hpet.clone(),
+                        interrupts: interrupts.clone(),
+                    },
+                    counter: mmio_perf.hpet.clone(),
+                })
+            },
+        );
 
         let ecam_cfg = PciEcamConfig {
             segment: firmware::bios::PCIE_ECAM_SEGMENT,
@@ -5486,10 +6911,27 @@ impl Machine {
             end_bus: firmware::bios::PCIE_ECAM_END_BUS,
         };
         let ecam_len = ecam_cfg.window_size_bytes();
-        self.mem
-            .map_mmio_once(firmware::bios::PCIE_ECAM_BASE, ecam_len, || {
-                Box::new(PciEcamMmio::new(pci_cfg, ecam_cfg))
-            });
+        self.mem.map_mmio_once(
+            "pcie-ecam",
+            firmware::bios::PCIE_ECAM_BASE,
+            ecam_len,
+            || {
+                Box::new(perf::CountedMmio {
+                    inner: PciEcamMmio::new(pci_cfg, ecam_cfg),
+                    counter: mmio_perf.ecam,
+                })
+            },
+        );
+    }
+
+    /// Ensure the synthetic USB HID topology exists on whichever controller is configured to host
+    /// it.
+    fn ensure_synthetic_usb_hid_topology(&mut self) {
+        if self.cfg.synthetic_usb_hid_on_xhci {
+            self.ensure_xhci_synthetic_usb_hid_topology();
+        } else {
+            self.ensure_uhci_synthetic_usb_hid_topology();
+        }
     }
 
     fn ensure_uhci_synthetic_usb_hid_topology(&mut self) {
@@ -5499,14 +6941,14 @@ impl Machine {
         {
             return;
         }
-        let Some(uhci) = &self.uhci else {
+        if self.uhci.is_none() {
             return;
-        };
-
-        let external_hub_root_port = usize::from(Self::UHCI_EXTERNAL_HUB_ROOT_PORT);
+        }
 
-        let mut uhci = uhci.borrow_mut();
-        let root_hub = uhci.controller_mut().hub_mut();
+        // The external hub lives on a physical port: with EHCI also enabled it sits on the shared
+        // USB2 mux and follows CONFIGFLAG/PORT_OWNER routing like any host-attached device.
+        let external_hub_root_port = Self::UHCI_EXTERNAL_HUB_ROOT_PORT;
+        let (ehci, uhci) = (self.ehci.clone(), self.uhci.clone());
 
         // Best-effort: ensure the canonical "external hub + synthetic HID" USB topology is present
         // without overwriting any host-attached devices.
@@ -5519,8 +6961,15 @@ impl Machine {
         // `RootHub::load_snapshot_ports` so the loader can reuse the existing instances (handle
         // stability). In the canonical case, machines that enable this feature also boot with this
         // topology, so this helper is sufficient.
-        if root_hub.port_device(external_hub_root_port).is_none() {
-            root_hub.attach(
+        let occupied = Self::with_usb_physical_port_device_mut(
+            ehci.as_ref(),
+            uhci.as_ref(),
+            external_hub_root_port,
+            |_| (),
+        )
+        .is_some();
+        if !occupied {
+            let _ = self.usb_attach_physical_port(
                 external_hub_root_port,
                 Box::new(UsbHubDevice::with_port_count(
                     Self::UHCI_EXTERNAL_HUB_PORT_COUNT,
@@ -5528,67 +6977,65 @@ impl Machine {
             );
         }
 
-        let Some(mut root_port0) = root_hub.port_device_mut(external_hub_root_port) else {
-            return;
+        let handles = SyntheticUsbHidHandles {
+            keyboard: &mut self.usb_hid_keyboard,
+            mouse: &mut self.usb_hid_mouse,
+            gamepad: &mut self.usb_hid_gamepad,
+            consumer_control: &mut self.usb_hid_consumer_control,
         };
-        let Some(port_count) = root_port0.model().hub_port_count() else {
-            // Root port 0 is occupied by a non-hub device; leave it alone.
+        Self::with_usb_physical_port_device_mut(
+            ehci.as_ref(),
+            uhci.as_ref(),
+            external_hub_root_port,
+            |root_port0| {
+                // Root port 0 may be occupied by a non-hub device; leave it alone.
+                if let Some(port_count) = root_port0.model().hub_port_count() {
+                    handles.attach_to_hub(root_port0.model_mut(), port_count);
+                }
+            },
+        );
+    }
+
+    /// xHCI counterpart of [`Machine::ensure_uhci_synthetic_usb_hid_topology`], used when
+    /// [`MachineConfig::synthetic_usb_hid_on_xhci`] is set. Same conservative rules apply.
+    fn ensure_xhci_synthetic_usb_hid_topology(&mut self) {
+        if !(self.cfg.enable_pc_platform
+            && self.cfg.enable_xhci
+            && self.cfg.enable_synthetic_usb_hid)
+        {
+            return;
+        }
+        let Some(xhci) = &self.xhci else {
             return;
         };
 
-        let hub = root_port0.model_mut();
+        let external_hub_root_port = usize::from(Self::XHCI_EXTERNAL_HUB_ROOT_PORT);
 
-        if port_count >= Self::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT
-            && hub
-                .hub_port_device_mut(Self::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT)
-                .is_err()
-        {
-            let keyboard = self
-                .usb_hid_keyboard
-                .get_or_insert_with(UsbHidKeyboardHandle::new)
-                .clone();
-            let _ = hub.hub_attach_device(
-                Self::UHCI_SYNTHETIC_HID_KEYBOARD_HUB_PORT,
-                Box::new(keyboard),
+        let mut xhci = xhci.borrow_mut();
+        let ctrl = xhci.controller_mut();
+        if ctrl.port_device(external_hub_root_port).is_none() {
+            ctrl.attach_device(
+                external_hub_root_port,
+                Box::new(UsbHubDevice::with_port_count(
+                    Self::XHCI_EXTERNAL_HUB_PORT_COUNT,
+                )),
             );
         }
-        if port_count >= Self::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT
-            && hub
-                .hub_port_device_mut(Self::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT)
-                .is_err()
-        {
-            let mouse = self
-                .usb_hid_mouse
-                .get_or_insert_with(UsbHidMouseHandle::new)
-                .clone();
-            let _ = hub.hub_attach_device(Self::UHCI_SYNTHETIC_HID_MOUSE_HUB_PORT, Box::new(mouse));
-        }
-        if port_count >= Self::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT
-            && hub
-                .hub_port_device_mut(Self::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT)
-                .is_err()
-        {
-            let gamepad = self
-                .usb_hid_gamepad
-                .get_or_insert_with(UsbHidGamepadHandle::new)
-                .clone();
-            let _ =
-                hub.hub_attach_device(Self::UHCI_SYNTHETIC_HID_GAMEPAD_HUB_PORT, Box::new(gamepad));
-        }
-        if port_count >= Self::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT
-            && hub
-                .hub_port_device_mut(Self::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT)
-                .is_err()
-        {
-            let consumer = self
-                .usb_hid_consumer_control
-                .get_or_insert_with(UsbHidConsumerControlHandle::new)
-                .clone();
-            let _ = hub.hub_attach_device(
-                Self::UHCI_SYNTHETIC_HID_CONSUMER_CONTROL_HUB_PORT,
-                Box::new(consumer),
-            );
+
+        let Some(root_port0) = ctrl.port_device_mut(external_hub_root_port) else {
+            return;
+        };
+        let Some(port_count) = root_port0.model().hub_port_count() else {
+            return;
+        };
+
+        SyntheticUsbHidHandles {
+            keyboard: &mut self.usb_hid_keyboard,
+            mouse: &mut self.usb_hid_mouse,
+            gamepad: &mut self.usb_hid_gamepad,
+            consumer_control: &mut self.usb_hid_consumer_control,
         }
+        .attach_to_hub(root_port0.model_mut(), port_count);
     }
 
     /// Returns the current CPU state.
@@ -5623,6 +7070,19 @@ impl Machine {
         &mut self.cpu.state
     }
 
+    /// Machine identification reported to the guest through the Aero CPUID leaves
+    /// ([`AERO_CPUID_BASE_LEAF`]) and extended feature MSRs ([`AERO_MSR_BASE`]).
+    ///
+    /// Derived from the machine configuration and the AeroGPU submission bridge state; the guest
+    /// view is refreshed on reset, snapshot restore and whenever the bridge is toggled.
+    pub fn paravirt_info(&self) -> ParavirtInfo {
+        let submission_bridge = self
+            .aerogpu_mmio
+            .as_ref()
+            .is_some_and(|dev| dev.borrow().submission_bridge_enabled());
+        ParavirtInfo::from_config(&self.cfg, submission_bridge)
+    }
+
     /// Debug/testing helper: returns mutable access to the CPU core for vCPU `idx`.
     ///
     /// # Panics
@@ -5666,6 +7126,199 @@ impl Machine {
         self.bios.smbios_eps_addr()
     }
 
+    /// Guest-physical memory map as presented by firmware.
+    ///
+    /// This is the same layout the BIOS derives its INT 15h E820 map from, at a finer grain: the
+    /// PCIe ECAM window, the PCI hole and the RAM remapped above 4GiB (only present when
+    /// `ram_size_bytes` exceeds the ECAM base), the ROM shadow area and the ACPI table windows
+    /// recorded during the last POST. Regions are sorted and non-overlapping.
+    pub fn guest_memory_map(&self) -> Vec<MemoryRegion> {
+        self.bios.guest_memory_map()
+    }
+
+    /// How guest-physical RAM maps onto the linear RAM backing, sorted by guest-physical address.
+    ///
+    /// The backing is the contiguous `[0..ram_size_bytes)` buffer (for example the host-provided
+    /// memory passed to [`Machine::new_with_guest_memory`]). Without the PCI hole this is a single
+    /// identity-mapped region; once `ram_size_bytes` exceeds the PCIe ECAM base, a second region
+    /// describes the RAM remapped above 4GiB. A host worker sharing the backing (e.g. a GPU worker
+    /// reading a guest-RAM framebuffer) can use this to translate an `FB_GPA` on its own.
+    pub fn guest_ram_layout(&self) -> Vec<RamRegionDescriptor> {
+        guest_ram::layout(self.cfg.ram_size_bytes)
+    }
+
+    /// Translate a guest-physical address into a byte offset inside the RAM backing.
+    ///
+    /// Returns `None` for addresses that are not guest RAM (the ECAM/PCI hole, MMIO, or beyond the
+    /// end of RAM). This is the translation [`Machine::display_present`] uses for scanout buffers
+    /// that live in guest RAM.
+    pub fn gpa_to_backing_offset(&self, gpa: u64) -> Option<u64> {
+        guest_ram::gpa_to_backing_offset(&self.guest_ram_layout(), gpa)
+    }
+
+    /// Debug/testing helper: RAM regions of [`Machine::guest_memory_map`] (what INT 15h E820
+    /// reports as usable) that intersect an MMIO mapping on the physical memory bus, paired with
+    /// the mapping's owner. Empty on a coherent machine; checked after every POST in debug builds.
+    pub fn e820_ram_mmio_overlaps(&self) -> Vec<(MemoryRegion, &'static str)> {
+        let mem = &self.mem;
+        self.guest_memory_map()
+            .into_iter()
+            .filter(|region| region.kind.is_ram())
+            .flat_map(|region| {
+                mem.mmio_owners_in(region.start, region.end())
+                    .map(move |owner| (region, owner))
+            })
+            .collect()
+    }
+
+    /// Debug/testing helper: describe every I/O range, MMIO range, IRQ and DMA channel devices
+    /// currently claim, next to what the published ACPI tables (FADT, MADT, HPET, MCFG and the
+    /// DSDT `_CRS`/`_PRT` objects) advertise.
+    ///
+    /// [`ResourceMap::mismatches`] lists inconsistencies in both directions: device resources the
+    /// tables fail to describe or reserve, and advertised resources no device decodes. ACPI checks
+    /// are skipped when firmware has not published tables.
+    pub fn debug_resource_map(&mut self) -> ResourceMap {
+        let mut claims = Vec::new();
+        let fixed = |owner, resource| ResourceClaim {
+            owner,
+            pci: None,
+            resource,
+        };
+
+        for claim in self.io.claims() {
+            claims.push(fixed(
+                claim.owner,
+                Resource::Io {
+                    start: claim.start,
+                    end: claim.end,
+                },
+            ));
+        }
+        // The aero-bench device is decoded by the CPU bus rather than registered on `self.io`.
+        if self.bench.is_some() {
+            claims.push(fixed(
+                "aero-bench",
+                Resource::Io {
+                    start: AERO_BENCH_PORT_BASE,
+                    end: AERO_BENCH_PORT_BASE + AERO_BENCH_PORT_COUNT - 1,
+                },
+            ));
+        }
+        for &(start, end, owner) in &self.mem.mapped_mmio {
+            claims.push(fixed(
+                owner,
+                Resource::Mmio {
+                    start,
+                    end: end - 1,
+                },
+            ));
+        }
+        // The LAPIC window is routed per vCPU rather than mapped on the shared bus.
+        claims.push(fixed(
+            "lapic",
+            Resource::Mmio {
+                start: LAPIC_MMIO_BASE,
+                end: LAPIC_MMIO_BASE + LAPIC_MMIO_SIZE - 1,
+            },
+        ));
+
+        let isa_irqs = [
+            (self.pit.is_some(), "pit8254", 0),
+            (self.i8042.is_some(), "i8042", 1),
+            (self.i8042.is_some(), "i8042", 12),
+            (self.fdc.is_some(), "fdc", FDC_IRQ),
+            (self.rtc.is_some(), "rtc-cmos", 8),
+            (self.acpi_pm.is_some(), "acpi-pm", ACPI_SCI_IRQ),
+            (self.ide_irq14_line.is_some(), "piix3-ide", 14),
+            (self.ide_irq15_line.is_some(), "piix3-ide", 15),
+        ];
+        for (present, owner, irq) in isa_irqs {
+            if present {
+                claims.push(fixed(owner, Resource::Irq(u32::from(irq))));
+            }
+        }
+        for &(irq, _) in &self.serial_irq_lines {
+            claims.push(fixed("serial16550", Resource::Irq(u32::from(irq))));
+        }
+
+        if self.fdc.is_some() {
+            claims.push(fixed(
+                "fdc",
+                Resource::Dma(aero_devices_storage::fdc::FDC_DMA_CHANNEL as u8),
+            ));
+        }
+        if let Some(dma) = &self.dma {
+            let dma = dma.borrow();
+            claims.push(fixed(
+                "dma8237",
+                Resource::Dma(aero_devices::dma::DMA_CASCADE_CHANNEL as u8),
+            ));
+            for channel in 0..aero_devices::dma::DMA_CHANNELS {
+                if dma.has_client(channel) {
+                    claims.push(fixed("isa-dma-client", Resource::Dma(channel as u8)));
+                }
+            }
+        }
+
+        let mut pci_intx = Vec::new();
+        if let Some(pci_cfg) = &self.pci_cfg {
+            let mut pci_cfg = pci_cfg.borrow_mut();
+            let bus = pci_cfg.bus_mut();
+            for bar in bus.mapped_bars() {
+                if bar.range.base == 0 || bar.range.size == 0 {
+                    continue;
+                }
+                let start = bar.range.base;
+                let end = start + bar.range.size - 1;
+                let resource = match bar.range.kind {
+                    aero_devices::pci::PciBarKind::Io => Resource::Io {
+                        start: start as u16,
+                        end: end as u16,
+                    },
+                    _ => Resource::Mmio { start, end },
+                };
+                claims.push(ResourceClaim {
+                    owner: "pci-bar",
+                    pci: Some(bar.bdf),
+                    resource,
+                });
+            }
+
+            let bdfs: Vec<PciBdf> = bus.iter_device_addrs().collect();
+            for bdf in bdfs {
+                let Some(pin) = bus
+                    .device_config_mut(bdf)
+                    .and_then(|cfg| PciInterruptPin::from_config_u8(cfg.interrupt_pin()))
+                else {
+                    continue;
+                };
+                let gsi = self.pci_intx.as_ref().and_then(|router| {
+                    let router = router.borrow();
+                    router.pirq_route(router.pirq_index(bdf, pin))
+                });
+                pci_intx.push(PciIntxRoute {
+                    bdf,
+                    pin: pin.index() as u8,
+                    gsi,
+                });
+                if let Some(gsi) = gsi {
+                    claims.push(ResourceClaim {
+                        owner: "pci-intx",
+                        pci: Some(bdf),
+                        resource: Resource::Irq(gsi),
+                    });
+                }
+            }
+        }
+
+        let acpi = self.acpi_rsdp_addr().map(|rsdp| {
+            let mem = &mut self.mem;
+            aero_acpi::read_acpi_resources(rsdp, |paddr, buf| mem.read_physical(paddr, buf))
+        });
+        ResourceMap::new(claims, pci_intx, acpi)
+    }
+
     /// Set the BIOS boot drive number exposed in `DL` when transferring control to the boot
     /// sector.
     ///
@@ -5775,12 +7428,110 @@ impl Machine {
         self.bios.set_cd_boot_drive(cd_boot_drive);
     }
 
-    /// Returns the configured vCPU count.
-    pub fn cpu_count(&self) -> usize {
-        self.cfg.cpu_count as usize
+    /// Set the firmware boot order (see [`MachineConfig::boot_order`]).
+    ///
+    /// An empty list restores the single-drive [`Machine::set_boot_drive`] policy.
+    ///
+    /// Call [`Machine::reset`] to apply the new order to the next boot.
+    pub fn set_boot_order(&mut self, boot_order: Vec<firmware::bios::BootEntry>) {
+        self.cfg.boot_order = boot_order.clone();
+        self.bios.set_boot_order(boot_order);
     }
 
-    /// Debug/testing helper: return a clone of a vCPU's architectural state.
+    /// Returns the configured firmware boot order.
+    pub fn boot_order(&self) -> &[firmware::bios::BootEntry] {
+        self.bios.boot_order()
+    }
+
+    /// Set the SMBIOS identity strings (see [`MachineConfig::smbios`]).
+    ///
+    /// Call [`Machine::reset`] to rebuild the SMBIOS tables with the new values.
+    pub fn set_smbios_identity(&mut self, identity: firmware::smbios::SmbiosIdentity) {
+        self.cfg.smbios = identity.clone();
+        self.bios.set_smbios_identity(identity);
+    }
+
+    /// Returns the SMBIOS identity published to the guest, with defaults filled in.
+    pub fn smbios_identity(&self) -> firmware::smbios::SmbiosIdentity {
+        self.bios.smbios_identity()
+    }
+
+    /// Show the firmware boot menu on the next [`Machine::reset`].
+    ///
+    /// POST then lists the detected boot devices on the BIOS console and waits for a selection
+    /// injected via [`Machine::inject_bios_key`]: `1`-`9` boot the corresponding entry, Enter boots
+    /// the configured order. Without input, the configured order is used once the timeout
+    /// (see [`Machine::set_boot_menu_timeout_ms`]) expires; each halted [`Machine::run_slice`] call
+    /// counts as 1ms.
+    pub fn request_boot_menu(&mut self) {
+        self.boot_menu_requested = true;
+    }
+
+    /// Set how long the firmware boot menu waits for a selection.
+    pub fn set_boot_menu_timeout_ms(&mut self, timeout_ms: u32) {
+        self.bios.set_boot_menu_timeout_ms(timeout_ms);
+    }
+
+    /// Returns `true` while the firmware boot menu is waiting for a selection.
+    pub fn boot_menu_active(&self) -> bool {
+        self.bios.boot_menu_active()
+    }
+
+    /// Returns the boot-order entry firmware booted from in the current boot session.
+    pub fn booted_entry(&self) -> Option<firmware::bios::BootEntry> {
+        self.bios.booted_entry()
+    }
+
+    /// Returns the configured vCPU count.
+    pub fn cpu_count(&self) -> usize {
+        self.cfg.cpu_count as usize
+    }
+
+    /// Returns `true` if every vCPU (the BSP and all APs, including APs still waiting for SIPI) is
+    /// halted.
+    pub fn all_vcpus_halted(&self) -> bool {
+        self.cpu.state.halted && self.ap_cpus.iter().all(|cpu| cpu.state.halted)
+    }
+
+    /// Earliest [`Machine::platform_clock`] time (in ns) at which an armed timer could raise an
+    /// interrupt, or `None` if no timer is armed (or the PC platform is disabled).
+    ///
+    /// Considers PIT channel 0, the RTC periodic/update/alarm interrupts, HPET comparators and
+    /// every vCPU's LAPIC timer. The result is conservative: it is never later than the first
+    /// timer interrupt (interrupt controller masking is not taken into account). Together with
+    /// [`Machine::all_vcpus_halted`], hosts can use it to sleep until the deadline instead of
+    /// calling `run_slice` on a fixed cadence while the guest is idle.
+    pub fn next_timer_deadline_ns(&self) -> Option<u64> {
+        let now_ns = self.platform_clock.as_ref()?.now_ns();
+
+        let pit = self
+            .pit
+            .as_ref()
+            .and_then(|pit| pit.borrow().ns_until_next_interrupt());
+        let rtc = self
+            .rtc
+            .as_ref()
+            .and_then(|rtc| rtc.borrow().ns_until_next_interrupt());
+        let hpet = self
+            .hpet
+            .as_ref()
+            .and_then(|hpet| hpet.borrow().ns_until_next_interrupt());
+        let lapic = self.interrupts.as_ref().and_then(|interrupts| {
+            interrupts
+                .borrow()
+                .lapics_iter()
+                .filter_map(|lapic| lapic.ns_until_timer_interrupt())
+                .min()
+        });
+
+        [pit, rtc, hpet, lapic]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|delta_ns| now_ns.saturating_add(delta_ns))
+    }
+
+    /// Debug/testing helper: return a clone of a vCPU's architectural state.
     ///
     /// vCPU0 is the BSP (`Machine::cpu()`); vCPU1..N-1 are APs.
     pub fn vcpu_state(&self, cpu_index: usize) -> Option<CpuState> {
@@ -5791,6 +7542,64 @@ impl Machine {
         }
     }
 
+    /// Queue a non-maskable interrupt for vCPU `cpu_index` (fault-injection testing).
+    ///
+    /// The NMI is delivered through IDT/IVT vector 2 at the next instruction boundary in
+    /// `run_slice`, ahead of external interrupts and regardless of `RFLAGS.IF`, and wakes the vCPU
+    /// from `HLT`. NMI blocking until the handler's `IRET` is not modeled.
+    pub fn inject_nmi(&mut self, cpu_index: usize) -> Result<(), InjectEventError> {
+        self.event_injector
+            .push(cpu_index, event_injection::InjectedEvent::Nmi)
+    }
+
+    /// Queue exception/interrupt `vector` for vCPU `cpu_index` (fault-injection testing).
+    ///
+    /// Architectural exception vectors (0..=31) are delivered at the next instruction boundary in
+    /// `run_slice` like a fault raised by the current instruction: they are not gated by
+    /// `RFLAGS.IF` and wake the vCPU from `HLT`. `error_code` is pushed only for exceptions that
+    /// architecturally carry one (0 is pushed if it is `None`); vector 2 is treated as
+    /// [`Machine::inject_nmi`]. Vectors >= 32 bypass the APIC and join the vCPU's external
+    /// interrupt queue, so they follow the normal `IF`/interrupt-shadow rules.
+    ///
+    /// Delivered events are counted like platform interrupts in
+    /// [`MachinePerfCounters::interrupt_injections`] and recorded in the hang watchdog's recent
+    /// vectors.
+    pub fn inject_exception(
+        &mut self,
+        cpu_index: usize,
+        vector: u8,
+        error_code: Option<u32>,
+    ) -> Result<(), InjectEventError> {
+        let event = event_injection::InjectedEvent::for_vector(vector, error_code)?;
+        self.event_injector.push(cpu_index, event)
+    }
+
+    /// Latch a system management interrupt.
+    ///
+    /// SMM is not modeled: the next `run_slice` drops the SMI and returns
+    /// [`RunExit::SmiUnsupported`] before executing any instruction.
+    pub fn inject_smi_stub(&mut self) {
+        self.event_injector.request_smi();
+    }
+
+    fn deliver_injected_event(&mut self, cpu_index: usize) {
+        let cpu = if cpu_index == 0 {
+            &mut self.cpu
+        } else {
+            match self.ap_cpus.get_mut(cpu_index - 1) {
+                Some(cpu) => cpu,
+                None => return,
+            }
+        };
+        let Some(vector) = self.event_injector.deliver(cpu_index, cpu) else {
+            return;
+        };
+        perf::bump(&mut self.perf.interrupt_injections);
+        if let Some(watchdog) = self.hang_watchdog.as_mut() {
+            watchdog.record_vector(vector);
+        }
+    }
+
     /// Replace the attached disk image.
     pub fn set_disk_image(&mut self, bytes: Vec<u8>) -> Result<(), MachineError> {
         self.disk.set_bytes(bytes)?;
@@ -5819,6 +7628,30 @@ impl Machine {
         Ok(())
     }
 
+    /// Grow the canonical disk to `new_capacity_bytes` while the guest is running.
+    ///
+    /// The backend must support [`aero_storage::VirtualDisk::resize`]; shrinking is rejected.
+    /// Controllers derived from the shared disk are rebuilt so ATA IDENTIFY and the virtio-blk
+    /// `capacity` field report the new size, and the guest is told about the change: AHCI latches
+    /// a port-change (PxIS.PCS) so the driver re-enumerates the port, and virtio-blk raises a
+    /// configuration-change interrupt.
+    pub fn resize_disk(&mut self, new_capacity_bytes: u64) -> Result<(), MachineError> {
+        aero_storage::VirtualDisk::resize(&mut self.disk, new_capacity_bytes)?;
+        self.attach_shared_disk_to_storage_controllers()?;
+
+        if self.ahci_port0_auto_attach_shared_disk {
+            if let Some(ahci) = &self.ahci {
+                ahci.borrow_mut().notify_device_changed(0);
+            }
+        }
+        if self.virtio_blk_auto_attach_shared_disk {
+            if let Some(virtio_blk) = &self.virtio_blk {
+                virtio_blk.borrow_mut().signal_config_interrupt();
+            }
+        }
+        Ok(())
+    }
+
     /// Returns a cloneable handle to the machine's canonical disk backend.
     ///
     /// This is the same disk used by BIOS INT13 services, and (when enabled) is also attached as
@@ -5913,6 +7746,24 @@ impl Machine {
         self.ide_primary_master_overlay = None;
     }
 
+    /// Set the overlay reference for the optional floppy image in drive A: (`disk_id=3`).
+    pub fn set_floppy_overlay_ref(
+        &mut self,
+        base_image: impl Into<String>,
+        overlay_image: impl Into<String>,
+    ) {
+        self.floppy_overlay = Some(snapshot::DiskOverlayRef {
+            disk_id: Self::DISK_ID_FLOPPY,
+            base_image: base_image.into(),
+            overlay_image: overlay_image.into(),
+        });
+    }
+
+    /// Clear the overlay reference for the optional floppy image in drive A: (`disk_id=3`).
+    pub fn clear_floppy_overlay_ref(&mut self) {
+        self.floppy_overlay = None;
+    }
+
     /// Return any disk overlay refs captured from the most recent snapshot restore.
     ///
     /// This is intended for host/coordinator code that needs to re-open and re-attach storage
@@ -5983,6 +7834,36 @@ impl Machine {
         }
     }
 
+    /// Borrow the host network backend for firmware PXE boot, paired with the guest NIC's MAC.
+    ///
+    /// Returns `None` when no NIC is enabled or no backend is attached. The backend must be handed
+    /// back with [`Machine::return_pxe_nic`] once POST (or the boot menu) is done with it.
+    fn take_pxe_nic(&mut self) -> Option<PxeBackendNic> {
+        let mac = if self.cfg.enable_e1000 {
+            self.cfg.e1000_mac_addr.unwrap_or(DEFAULT_E1000_MAC_ADDR)
+        } else if self.cfg.enable_virtio_net {
+            self.cfg
+                .virtio_net_mac_addr
+                .unwrap_or(DEFAULT_VIRTIO_NET_MAC_ADDR)
+        } else {
+            return None;
+        };
+        let backend = match &self.virtio_net {
+            Some(virtio) => virtio
+                .borrow_mut()
+                .device_mut::<VirtioNet<VirtioNetBackendAdapter>>()
+                .and_then(|net| net.backend_mut().take_backend()),
+            None => self.network_backend.take(),
+        }?;
+        Some(PxeBackendNic { mac, backend })
+    }
+
+    fn return_pxe_nic(&mut self, nic: Option<PxeBackendNic>) {
+        if let Some(nic) = nic {
+            self.set_network_backend(nic.backend);
+        }
+    }
+
     /// Return best-effort stats for the attached `NET_TX`/`NET_RX` ring backend (if present).
     pub fn network_backend_l2_ring_stats(&self) -> Option<L2TunnelRingBackendStats> {
         if let Some(virtio) = &self.virtio_net {
@@ -6043,6 +7924,7 @@ impl Machine {
             interrupts,
             ApCpus::All(self.ap_cpus.as_mut_slice()),
             &mut self.mem,
+            None,
         );
         aero_mmu::MemoryBus::read_u32(&mut bus, LAPIC_MMIO_BASE + offset)
     }
@@ -6063,6 +7945,7 @@ impl Machine {
             interrupts,
             ApCpus::All(self.ap_cpus.as_mut_slice()),
             &mut self.mem,
+            None,
         );
         aero_mmu::MemoryBus::write_u32(&mut bus, LAPIC_MMIO_BASE + offset, value);
     }
@@ -6145,16 +8028,125 @@ impl Machine {
         self.mem.write_physical(paddr, data);
     }
 
+    /// Debug helper: translate `vaddr` to a guest physical address through vCPU `cpu_index`'s
+    /// current page tables (CR0/CR3/CR4/EFER), as a supervisor read.
+    ///
+    /// Handles no paging, 32-bit, PAE and 4-level paging including large pages. The walk does not
+    /// set accessed/dirty bits or touch the TLB used for execution; an unmapped address reports
+    /// the paging-structure level whose entry was not present.
+    pub fn translate_virtual(
+        &mut self,
+        cpu_index: usize,
+        vaddr: u64,
+    ) -> Result<u64, VirtTranslateError> {
+        self.translate_virtual_access(cpu_index, vaddr, aero_mmu::AccessType::Read)
+    }
+
+    /// Debug helper: read `len` bytes starting at virtual address `vaddr` as seen by vCPU
+    /// `cpu_index` (see [`Machine::translate_virtual`]). Ranges may cross page boundaries; the
+    /// first unmapped page fails the whole read.
+    pub fn read_virtual_bytes(
+        &mut self,
+        cpu_index: usize,
+        vaddr: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, VirtTranslateError> {
+        let mut out = vec![0u8; len];
+        let mut pos = 0;
+        for (chunk_vaddr, n) in virt_debug::page_chunks(vaddr, len) {
+            let paddr = self.translate_virtual(cpu_index, chunk_vaddr)?;
+            self.mem.read_physical(paddr, &mut out[pos..pos + n]);
+            pos += n;
+        }
+        Ok(out)
+    }
+
+    /// Debug helper: write `data` starting at virtual address `vaddr` as seen by vCPU `cpu_index`.
+    ///
+    /// With `bypass_permissions` false the write is checked like a supervisor write, so read-only
+    /// pages fail with [`VirtTranslateError::WriteProtected`] while `CR0.WP` is set. With it true,
+    /// any present mapping is written (e.g. to patch code pages). Every page is translated before
+    /// anything is written, so a failed write leaves memory unchanged.
+    pub fn write_virtual_bytes(
+        &mut self,
+        cpu_index: usize,
+        vaddr: u64,
+        data: &[u8],
+        bypass_permissions: bool,
+    ) -> Result<(), VirtTranslateError> {
+        let access = if bypass_permissions {
+            aero_mmu::AccessType::Read
+        } else {
+            aero_mmu::AccessType::Write
+        };
+        let chunks = virt_debug::page_chunks(vaddr, data.len())
+            .map(|(chunk_vaddr, n)| {
+                Ok((
+                    self.translate_virtual_access(cpu_index, chunk_vaddr, access)?,
+                    n,
+                ))
+            })
+            .collect::<Result<Vec<_>, VirtTranslateError>>()?;
+        let mut pos = 0;
+        for (paddr, n) in chunks {
+            self.mem.write_physical(paddr, &data[pos..pos + n]);
+            pos += n;
+        }
+        Ok(())
+    }
+
+    fn translate_virtual_access(
+        &mut self,
+        cpu_index: usize,
+        vaddr: u64,
+        access: aero_mmu::AccessType,
+    ) -> Result<u64, VirtTranslateError> {
+        let state = if cpu_index == 0 {
+            &self.cpu.state
+        } else {
+            match self.ap_cpus.get(cpu_index - 1) {
+                Some(cpu) => &cpu.state,
+                None => return Err(VirtTranslateError::InvalidCpuIndex(cpu_index)),
+            }
+        };
+        virt_debug::translate(state, &mut self.mem, vaddr, access)
+    }
+
     /// Debug/testing helper: read from an I/O port.
     pub fn io_read(&mut self, port: u16, size: u8) -> u32 {
+        if let Some(bench) = self.bench.as_ref().filter(|_| BenchDevice::decodes(port)) {
+            return bench.read(port, size);
+        }
         self.io.read(port, size)
     }
 
     /// Debug/testing helper: write to an I/O port.
     pub fn io_write(&mut self, port: u16, size: u8, value: u32) {
+        if self.bench.is_some() && BenchDevice::decodes(port) {
+            let tsc = self.cpu.state.msr.tsc;
+            let now_ns = self.guest_now_ns();
+            if let Some(bench) = self.bench.as_mut() {
+                bench.write(port, size, value, tsc, now_ns, &mut self.mem);
+            }
+            return;
+        }
         self.io.write(port, size, value);
     }
 
+    /// Returns the aero-bench counters, or `None` if [`MachineConfig::enable_aero_bench`] is off.
+    ///
+    /// Counters are cleared by [`Machine::reset`] and [`Machine::clear_bench_results`].
+    pub fn bench_results(&self) -> Option<BenchResults> {
+        self.bench.as_ref().map(BenchDevice::results)
+    }
+
+    /// Clear the aero-bench counters (e.g. between benchmark phases).
+    pub fn clear_bench_results(&mut self) {
+        if let Some(bench) = self.bench.as_mut() {
+            bench.clear_results();
+        }
+    }
+
     // ---------------------------------------------------------------------
     // Host-facing display API (VGA/VBE scanout)
     // ---------------------------------------------------------------------
@@ -6236,7 +8228,298 @@ impl Machine {
     ///
     /// Otherwise (no VGA, no AeroGPU fallback), this clears the cached framebuffer and returns
     /// `(0, 0)` resolution.
-    pub fn display_present(&mut self) {
+    ///
+    /// Unless `force` is set, this is a cheap no-op that keeps the cached framebuffer when
+    /// [`Machine::display_generation`] has not changed since the last present. Frames read from
+    /// state the generation cannot observe (a WDDM scanout or cursor in guest RAM, an in-process
+    /// backend scanout, or a VBE LFB outside VRAM) are always re-rendered, as are text frames
+    /// whose blinking characters or cursor changed phase with guest time.
+    ///
+    /// Returns whether the framebuffer was re-rendered.
+    pub fn display_present(&mut self, force: bool) -> bool {
+        let generation = self.display_generation.get();
+        if !force
+            && self.display_presented_generation == Some(generation)
+            && !self.display_blink_refresh_pending()
+        {
+            self.record_display_frame(false);
+            return false;
+        }
+
+        self.display_present_untracked = false;
+        self.display_text_blink_epoch = None;
+        self.display_render();
+        self.display_presented_generation = (!self.display_present_untracked).then_some(generation);
+
+        #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+        if let (Some(slots), Some(scanout_state)) = (self.scanout_frame_slots, &self.scanout_state)
+        {
+            if !self.display_fb.is_empty() {
+                let _ = slots.try_publish_frame(
+                    scanout_state,
+                    self.display_width,
+                    self.display_height,
+                    &self.display_fb,
+                );
+            }
+        }
+        self.record_display_frame(true);
+        true
+    }
+
+    /// Start recording the frames produced by [`Machine::display_present`] into a bounded queue
+    /// (see [`Machine::take_recorded_frames`]).
+    ///
+    /// A frame is recorded when a present shows content that differs from the last recorded
+    /// frame and at least `min_frame_interval_guest_ns` of guest time has passed since it; a
+    /// changed frame held back by the interval is recorded by the first present after the
+    /// interval elapses. Identical consecutive frames are never recorded twice. With `scale`, each
+    /// frame is box-filtered from the presented framebuffer to the requested size. When
+    /// `max_frames` frames are queued, the oldest one is evicted and counted in
+    /// [`RecordedFrames::dropped_frames`].
+    ///
+    /// Recording is host configuration: it is not snapshotted, and it stays enabled across
+    /// [`Machine::reset`] and snapshot restore. Calling this while recording discards the queued
+    /// frames and restarts with the new configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_frames` is zero or `scale` has a zero dimension.
+    pub fn start_display_recording(&mut self, config: DisplayRecordingConfig) {
+        assert!(
+            config.max_frames != 0,
+            "display recording max_frames must be non-zero"
+        );
+        assert!(
+            config.scale.is_none_or(|(w, h)| w != 0 && h != 0),
+            "display recording scale must be non-zero"
+        );
+        self.display_recorder = Some(display_recording::DisplayRecorder::new(config));
+    }
+
+    /// Stop display recording and drop the queued frames.
+    pub fn stop_display_recording(&mut self) {
+        self.display_recorder = None;
+    }
+
+    /// Remove and return the recorded frames, oldest first, with the number of frames evicted
+    /// since the previous call. Returns an empty set if recording is not enabled.
+    pub fn take_recorded_frames(&mut self) -> RecordedFrames {
+        self.display_recorder
+            .as_mut()
+            .map(|recorder| recorder.take())
+            .unwrap_or_default()
+    }
+
+    fn record_display_frame(&mut self, rendered: bool) {
+        if self.display_recorder.is_none() {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        if let Some(recorder) = self.display_recorder.as_mut() {
+            recorder.offer(
+                now_ns,
+                rendered,
+                self.display_width,
+                self.display_height,
+                &self.display_fb,
+            );
+        }
+    }
+
+    /// Monotonic display damage counter.
+    ///
+    /// Bumped by guest writes that can change presented pixels: legacy VGA memory (including text
+    /// mode at `0xB8000`), VBE LFB / AeroGPU BAR1 VRAM, AeroGPU scanout0 and cursor registers, VGA
+    /// port writes (DAC palette, CRTC cursor/start address, Bochs VBE_DISPI), and BIOS INT 10h
+    /// services (mode sets, BDA cursor/page updates). Guest reads and
+    /// [`Machine::display_present`] never bump it. The value is preserved by snapshots.
+    pub fn display_generation(&self) -> u64 {
+        self.display_generation.get()
+    }
+
+    /// Coarse boot progress, derived from architectural events only (see [`BootStage`]).
+    ///
+    /// Poll [`Machine::boot_stage_changes`] to cheaply detect updates. The stage restarts at
+    /// [`BootStage::Firmware`] whenever the machine resets through firmware POST (or via
+    /// [`Machine::boot_direct`]); a shutdown-status resume keeps it. Not part of snapshots.
+    pub fn boot_stage(&self) -> BootStage {
+        self.boot_stage.stage()
+    }
+
+    /// Counter bumped on every [`BootStage`] transition and on every stage reset.
+    pub fn boot_stage_changes(&self) -> u64 {
+        self.boot_stage.changes()
+    }
+
+    /// Stage transitions since the stage last restarted, oldest first, with guest timestamps for
+    /// boot-time profiling.
+    pub fn boot_stage_transitions(&self) -> Vec<BootStageTransition> {
+        self.boot_stage.transitions().to_vec()
+    }
+
+    /// Guest time in nanoseconds, used to timestamp boot stage transitions and to time pointer
+    /// coalescing windows.
+    fn guest_now_ns(&self) -> u64 {
+        guest_now_ns(
+            self.platform_clock.as_ref(),
+            self.cpu.state.msr.tsc,
+            self.cpu.time.tsc_hz(),
+        )
+    }
+
+    /// Move the POST codes the HLE BIOS queued during its last call into the port 0x80 history.
+    fn drain_bios_post_codes(&mut self) {
+        let codes = self.bios.take_post_codes();
+        if codes.is_empty() {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        for code in codes {
+            self.post_codes.record(code, now_ns);
+        }
+    }
+
+    fn advance_boot_stage(&mut self, stage: BootStage) {
+        if stage <= self.boot_stage.stage() {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        if self.boot_stage.advance(stage, now_ns) {
+            self.events
+                .record_at(now_ns, machine_events::MachineEventKind::BootStage(stage));
+        }
+    }
+
+    fn pci_config_write_count(&self) -> u64 {
+        self.pci_cfg.as_ref().map_or(0, |pci_cfg| {
+            pci_cfg.borrow_mut().bus_mut().config_write_count()
+        })
+    }
+
+    /// Sample BSP state for the mode-switch and ring-0 PCI enumeration boot stages.
+    ///
+    /// `pci_config_writes_before_batch` is the PCI config write count from before the batch that
+    /// just ran; it stands in for the count at the mode switch, which happened somewhere inside
+    /// that batch.
+    fn observe_boot_stage_cpu(&mut self, pci_config_writes_before_batch: u64) {
+        let mut stage = self.boot_stage.stage();
+        if stage < BootStage::KernelStarting
+            && matches!(self.cpu.state.mode, CpuMode::Protected | CpuMode::Long)
+        {
+            self.boot_stage
+                .set_pci_config_writes_at_mode_switch(pci_config_writes_before_batch);
+            self.advance_boot_stage(BootStage::KernelStarting);
+            stage = self.boot_stage.stage();
+        }
+        if stage == BootStage::KernelStarting
+            && self.cpu.state.cpl() == 0
+            && self.pci_config_write_count() != self.boot_stage.pci_config_writes_at_mode_switch()
+        {
+            self.advance_boot_stage(BootStage::DeviceEnumeration);
+        }
+    }
+
+    /// Host-visible performance counters accumulated since construction or the last
+    /// [`Machine::reset_perf_counters`] call.
+    ///
+    /// Counters are host diagnostics (e.g. for a "MIPS" readout or spotting guest MMIO polling
+    /// loops): they are not snapshotted and are not cleared by [`Machine::reset`] or snapshot
+    /// restore.
+    pub fn perf_counters(&self) -> MachinePerfCounters {
+        let mut counters = self.perf.clone();
+        for (total, &pending) in counters
+            .assist_exits
+            .iter_mut()
+            .zip(self.assist.assist_exits.iter())
+        {
+            *total = total.wrapping_add(pending);
+        }
+        counters.mmio = self.mmio_perf.get();
+        counters
+    }
+
+    /// Take the report of unimplemented instructions, refused MSR accesses and retried all-zero
+    /// `CPUID` leaves collected since construction or the previous call, resetting the collector.
+    ///
+    /// Like [`Machine::perf_counters`], this is host diagnostics: it is not snapshotted and is not
+    /// cleared by [`Machine::reset`] or snapshot restore, so one report can span a whole boot
+    /// attempt including guest reboots.
+    pub fn take_unimplemented_report(&mut self) -> UnimplementedReport {
+        unimplemented_report::build(self.assist.telemetry.take())
+    }
+
+    /// Zero all performance counters (see [`Machine::perf_counters`]).
+    pub fn reset_perf_counters(&mut self) {
+        self.perf = MachinePerfCounters::new(usize::from(self.cfg.cpu_count));
+        self.assist.assist_exits = Default::default();
+        self.mmio_perf.clear();
+    }
+
+    /// Enable (the default) or disable device tick scheduling.
+    ///
+    /// With scheduling, [`Machine::tick_platform`] and [`Machine::poll_network`] skip devices that
+    /// have nothing to do: halted UHCI/EHCI controllers whose root ports are quiet, an E1000 with
+    /// no host backend and nothing queued, and AeroGPU while scanout is disabled. The HPET is only
+    /// polled at its next comparator match or after a guest write to its MMIO window. Skipped ticks
+    /// are no-ops for the device, so interrupt timing does not change; [`Machine::perf_counters`]
+    /// reports executed and skipped ticks per device.
+    ///
+    /// Disabling scheduling ticks every device on every step, which is useful to check that a
+    /// workload behaves identically either way. The setting is host configuration: it is not
+    /// snapshotted and survives [`Machine::reset`]. Toggling it makes every device tick once.
+    pub fn set_device_tick_scheduling(&mut self, enabled: bool) {
+        self.tick_scheduler.set_enabled(enabled);
+    }
+
+    /// Whether device tick scheduling is enabled (see [`Machine::set_device_tick_scheduling`]).
+    pub fn device_tick_scheduling(&self) -> bool {
+        self.tick_scheduler.enabled()
+    }
+
+    /// Bring devices whose ticks were skipped up to date so saved state does not depend on device
+    /// tick scheduling: the HPET main counter and AeroGPU's cached time are only advanced lazily.
+    fn sync_skipped_device_ticks(&self) {
+        if !self.tick_scheduler.enabled() {
+            return;
+        }
+        if let (Some(hpet), Some(interrupts)) = (&self.hpet, &self.interrupts) {
+            if self.tick_scheduler.is_parked(TickDevice::Hpet) {
+                hpet.borrow_mut().poll(&mut *interrupts.borrow_mut());
+            }
+        }
+        if let (Some(dev), Some(clock)) = (&self.aerogpu_mmio, &self.platform_clock) {
+            let mut dev = dev.borrow_mut();
+            if dev.vblank_idle() {
+                dev.tick_vblank(clock.now_ns());
+            }
+        }
+    }
+
+    fn reset_assist_context(&mut self) {
+        // Assist exit counts and telemetry live in the assist context; carry them into the machine
+        // counters (resp. the rebuilt context) so they survive the context being rebuilt.
+        for (total, &pending) in self
+            .perf
+            .assist_exits
+            .iter_mut()
+            .zip(self.assist.assist_exits.iter())
+        {
+            *total = total.wrapping_add(pending);
+        }
+        self.assist = AssistContext {
+            telemetry: self.assist.telemetry.take(),
+            ..AssistContext::default()
+        };
+        self.refresh_paravirt_info();
+    }
+
+    /// Republish [`Machine::paravirt_info`] through CPUID and the Aero MSRs.
+    fn refresh_paravirt_info(&mut self) {
+        self.assist.features.hypervisor = Some(self.paravirt_info().hypervisor_interface());
+    }
+
+    fn display_render(&mut self) {
         if let Some(vga) = &self.vga {
             let mut vga = vga.borrow_mut();
             vga.present();
@@ -6258,6 +8541,7 @@ impl Machine {
                 return;
             }
             if self.display_present_aerogpu_backend_scanout() {
+                self.display_present_untracked = true;
                 return;
             }
             if self.display_present_aerogpu_vbe_lfb() {
@@ -6400,8 +8684,7 @@ impl Machine {
         let vram_fast = if pitch_usize != 0 && pitch_usize >= row_bytes {
             match (self.aerogpu.clone(), self.aerogpu_bar1_base()) {
                 (Some(aerogpu), Some(bar1_base)) => {
-                    let bar1_end =
-                        bar1_base.saturating_add(aero_devices::pci::profile::AEROGPU_VRAM_SIZE);
+                    let bar1_end = bar1_base.saturating_add(self.aerogpu_vram_bar_size());
                     if base < bar1_base || base >= bar1_end {
                         None
                     } else {
@@ -6437,6 +8720,8 @@ impl Machine {
         } else {
             None
         };
+        // LFB reads outside BAR1 hit guest RAM, which the display generation does not observe.
+        self.display_present_untracked = vram_fast.is_none();
 
         match bpp {
             32 => {
@@ -6541,27 +8826,14 @@ impl Machine {
                 // Prefer the AeroGPU-emulated DAC palette so port writes affect visible output.
                 // The BIOS VBE palette is mirrored into this DAC on reset and when the guest uses
                 // INT 10h AX=4F09 "Set Palette Data" (see `handle_bios_interrupt`).
-                let (pal, pel_mask) = self
+                //
+                // The lookup table covers each possible 8-bit pixel value (after applying the PEL
+                // mask).
+                let lut = self
                     .aerogpu
                     .as_ref()
-                    .map(|dev| {
-                        let dev = dev.borrow();
-                        (dev.dac_palette, dev.pel_mask)
-                    })
-                    .unwrap_or(([[0u8; 3]; 256], 0xFF));
-                let scale_6bit_to_8bit = |c: u8| -> u8 { (c << 2) | (c >> 4) };
-
-                // Precompute a lookup table for each possible 8-bit pixel value (after applying
-                // PEL mask).
-                let mut lut = [0u32; 256];
-                for (idx, out) in lut.iter_mut().enumerate() {
-                    let pal_idx = (idx as u8 & pel_mask) as usize;
-                    let [r6, g6, b6] = pal[pal_idx];
-                    let b = scale_6bit_to_8bit(b6);
-                    let g = scale_6bit_to_8bit(g6);
-                    let r = scale_6bit_to_8bit(r6);
-                    *out = 0xFF00_0000 | (u32::from(b) << 16) | (u32::from(g) << 8) | u32::from(r);
-                }
+                    .map(|dev| dev.borrow().dac.palette_as_rgba8888())
+                    .unwrap_or([0xFF00_0000; 256]);
 
                 if let Some((aerogpu, vram_off, pitch_bytes)) = vram_fast.as_ref() {
                     let dev = aerogpu.borrow();
@@ -6625,23 +8897,8 @@ impl Machine {
         const PIXELS: usize = WIDTH * HEIGHT;
         const WINDOW_BYTES: usize = 64 * 1024;
 
-        let (pal, pel_mask) = {
-            let dev = aerogpu.borrow();
-            (dev.dac_palette, dev.pel_mask)
-        };
-
-        let scale_6bit_to_8bit = |c: u8| -> u8 { (c << 2) | (c >> 4) };
-
-        // Precompute a lookup table for each possible 8-bit pixel value (after applying PEL mask).
-        let mut lut = [0u32; 256];
-        for (idx, out) in lut.iter_mut().enumerate() {
-            let pal_idx = (idx as u8 & pel_mask) as usize;
-            let [r6, g6, b6] = pal[pal_idx];
-            let b = scale_6bit_to_8bit(b6);
-            let g = scale_6bit_to_8bit(g6);
-            let r = scale_6bit_to_8bit(r6);
-            *out = 0xFF00_0000 | (u32::from(b) << 16) | (u32::from(g) << 8) | u32::from(r);
-        }
+        // Lookup table for each possible 8-bit pixel value (after applying the PEL mask).
+        let lut = aerogpu.borrow().dac.palette_as_rgba8888();
 
         self.display_width = WIDTH as u32;
         self.display_height = HEIGHT as u32;
@@ -6710,6 +8967,8 @@ impl Machine {
                 .unwrap_or(0)
         };
         if (command & (1 << 2)) == 0 {
+            // PCI COMMAND writes do not bump the display generation.
+            self.display_present_untracked = true;
             self.display_fb.clear();
             self.display_width = 0;
             self.display_height = 0;
@@ -6726,8 +8985,8 @@ impl Machine {
         //
         // Detect BAR1-backed surfaces and read directly from `AeroGpuDevice.vram` instead.
         let bar1_base = self.aerogpu_bar1_base();
-        let bar1_end = bar1_base
-            .and_then(|base| base.checked_add(aero_devices::pci::profile::AEROGPU_VRAM_SIZE));
+        let bar1_size = self.aerogpu_vram_bar_size();
+        let bar1_end = bar1_base.and_then(|base| base.checked_add(bar1_size));
 
         let scanout_row_bytes = || -> Option<u64> {
             let bytes_per_pixel = u64::try_from(
@@ -6737,52 +8996,55 @@ impl Machine {
             u64::from(state.width).checked_mul(bytes_per_pixel)
         };
 
+        // Exclusive end of the bytes scanout reads, if the configuration is readable at all.
+        let scanout_end_gpa = || -> Option<u64> {
+            let row_bytes = scanout_row_bytes()?;
+            let pitch = u64::from(state.pitch_bytes);
+            if pitch == 0 || pitch < row_bytes {
+                return None;
+            }
+            let last_row = u64::from(state.height).checked_sub(1)?.checked_mul(pitch)?;
+            state.fb_gpa.checked_add(last_row)?.checked_add(row_bytes)
+        };
+
         let scanout_is_bar1_backed = || -> bool {
             let (Some(bar1_base), Some(bar1_end)) = (bar1_base, bar1_end) else {
                 return false;
             };
-            let Some(row_bytes) = scanout_row_bytes() else {
-                return false;
-            };
             if state.fb_gpa < bar1_base || state.fb_gpa >= bar1_end {
                 return false;
             }
-            let pitch = u64::from(state.pitch_bytes);
-            if pitch == 0 {
+            scanout_end_gpa().is_some_and(|end_gpa| end_gpa <= bar1_end)
+        };
+
+        // Scanout buffers that lie entirely in guest RAM are read straight from RAM, resolved
+        // through the same layout `Machine::guest_ram_layout` publishes to hosts sharing the
+        // backing, so a worker translating `FB_GPA` itself sees exactly these bytes.
+        let ram_layout = self.guest_ram_layout();
+        let scanout_is_ram_backed = || -> bool {
+            scanout_end_gpa().is_some_and(|end_gpa| {
+                guest_ram::gpa_range_to_backing_offset(
+                    &ram_layout,
+                    state.fb_gpa,
+                    end_gpa - state.fb_gpa,
+                )
+                .is_some()
+            })
+        };
+
+        let cursor_is_bar1_backed = || -> bool {
+            if !cursor.enable {
                 return false;
             }
-            if pitch < row_bytes {
+            let (Some(bar1_base), Some(bar1_end)) = (bar1_base, bar1_end) else {
                 return false;
-            }
-            let height = u64::from(state.height);
-            if height == 0 {
+            };
+            if cursor.fb_gpa < bar1_base || cursor.fb_gpa >= bar1_end {
                 return false;
             }
-            let Some(last_row) = height.checked_sub(1).and_then(|h| h.checked_mul(pitch)) else {
-                return false;
-            };
-            let Some(last_row_gpa) = state.fb_gpa.checked_add(last_row) else {
-                return false;
-            };
-            let Some(end_gpa) = last_row_gpa.checked_add(row_bytes) else {
-                return false;
-            };
-            end_gpa <= bar1_end
-        };
-
-        let cursor_is_bar1_backed = || -> bool {
-            if !cursor.enable {
-                return false;
-            }
-            let (Some(bar1_base), Some(bar1_end)) = (bar1_base, bar1_end) else {
-                return false;
-            };
-            if cursor.fb_gpa < bar1_base || cursor.fb_gpa >= bar1_end {
-                return false;
-            }
-            let width = u64::from(cursor.width);
-            let height = u64::from(cursor.height);
-            if width == 0 || height == 0 {
+            let width = u64::from(cursor.width);
+            let height = u64::from(cursor.height);
+            if width == 0 || height == 0 {
                 return false;
             }
 
@@ -6814,6 +9076,11 @@ impl Machine {
         };
 
         let cursor_in_vram = cursor_is_bar1_backed() && self.aerogpu.is_some();
+        let scanout_in_vram = scanout_is_bar1_backed() && self.aerogpu.is_some();
+        let scanout_in_ram = !scanout_in_vram && scanout_is_ram_backed();
+        // Only BAR1 VRAM writes bump the display generation; guest RAM surfaces and backend output
+        // must be re-read on every present.
+        self.display_present_untracked = !scanout_in_vram || (cursor.enable && !cursor_in_vram);
 
         // If an in-process AeroGPU backend is installed and has a presented scanout, prefer that
         // output over the guest-provided scanout buffer. This is primarily used by native/test
@@ -6823,6 +9090,7 @@ impl Machine {
             let mut dev = aerogpu.borrow_mut();
             dev.read_backend_scanout_rgba8888(0)
         } {
+            self.display_present_untracked = true;
             if cursor.enable {
                 let cursor_fb = if cursor_in_vram {
                     let aerogpu = self.aerogpu.as_ref().expect("checked above");
@@ -6847,13 +9115,15 @@ impl Machine {
             return true;
         }
 
-        let scanout_in_vram = scanout_is_bar1_backed() && self.aerogpu.is_some();
         let Some(mut fb) = (if scanout_in_vram {
             // Avoid borrowing `self.mem` while holding the AeroGPU VRAM borrow.
             let aerogpu = self.aerogpu.as_ref().expect("checked above");
             let dev = aerogpu.borrow();
             let mut vram_bus = AeroGpuBar1VramReadbackBus::new(&dev.vram, bar1_base.unwrap_or(0));
             state.read_rgba8888(&mut vram_bus)
+        } else if scanout_in_ram {
+            let mut ram_bus = guest_ram::GuestRamReadbackBus::new(self.mem.bus.ram(), &ram_layout);
+            state.read_rgba8888(&mut ram_bus)
         } else {
             state.read_rgba8888(&mut self.mem)
         }) else {
@@ -6958,26 +9228,100 @@ impl Machine {
     fn display_present_aerogpu_text_mode(&mut self) {
         // Avoid holding a `RefCell` borrow of the AeroGPU device while reading from guest memory:
         // legacy VRAM is MMIO-routed back into the same device and may borrow it again.
-        let (pel_mask, dac_palette, attr_regs) = if let Some(aerogpu) = &self.aerogpu {
+        let regs = if let Some(aerogpu) = &self.aerogpu {
             let dev = aerogpu.borrow();
-            (dev.pel_mask, dev.dac_palette, dev.attr_regs)
+            aerogpu_legacy_text::VgaTextRegs {
+                palette: dev.dac.palette_as_rgba8888(),
+                attr_regs: dev.attr_regs,
+                clocking_mode: dev.seq_regs[0x01],
+                underline_location: dev.crtc_regs[0x14],
+            }
         } else {
-            (
-                0xFF,
-                AeroGpuDevice::default_dac_palette(),
-                AeroGpuDevice::default_attr_regs(),
-            )
+            // Without AeroGPU the text buffer is plain RAM.
+            self.display_present_untracked = true;
+            aerogpu_legacy_text::VgaTextRegs {
+                palette: VgaDac::new().palette_as_rgba8888(),
+                attr_regs: AeroGpuDevice::default_attr_regs(),
+                clocking_mode: 0,
+                underline_location: 0,
+            }
         };
 
-        let (w, h) = aerogpu_legacy_text::render_into(
+        let blink_epoch = aero_gpu_vga::text_blink_epoch(self.display_guest_time_ns());
+        let (w, h, blink_sensitive) = aerogpu_legacy_text::render_into(
             &mut self.display_fb,
             &mut self.mem,
-            &dac_palette,
-            pel_mask,
-            &attr_regs,
+            &regs,
+            blink_epoch,
         );
         self.display_width = w;
         self.display_height = h;
+        self.display_text_blink_epoch = blink_sensitive.then_some(blink_epoch);
+    }
+
+    /// Guest time used for display effects (text blink), taken from the platform clock.
+    fn display_guest_time_ns(&self) -> u64 {
+        self.platform_clock
+            .as_ref()
+            .map_or(0, |clock| clock.now_ns())
+    }
+
+    /// Whether the cached frame shows blinking text or a cursor whose blink phase has changed
+    /// since it was rendered.
+    fn display_blink_refresh_pending(&self) -> bool {
+        if let Some(vga) = &self.vga {
+            return vga.borrow().text_blink_refresh_pending();
+        }
+        self.display_text_blink_epoch.is_some_and(|epoch| {
+            epoch != aero_gpu_vga::text_blink_epoch(self.display_guest_time_ns())
+        })
+    }
+
+    /// Read the visible legacy text-mode screen as characters and attributes.
+    ///
+    /// Decodes the same state [`Machine::display_present`] draws from: the VGA device's VRAM and
+    /// CRTC start address/cursor when it is enabled, otherwise the `0xB8000` buffer (AeroGPU's
+    /// legacy VRAM alias, or plain RAM) at the BDA's active page and cursor. Returns `None` when
+    /// the display is not in a text mode (VGA/VBE graphics, or AeroGPU WDDM scanout).
+    pub fn read_text_screen(&mut self) -> Option<TextScreen> {
+        if let Some(vga) = &self.vga {
+            let snap = vga.borrow().text_snapshot()?;
+            let cursor_u8 = |v: usize| u8::try_from(v).unwrap_or(u8::MAX);
+            return Some(TextScreen::from_cells(
+                snap.cols,
+                snap.rows,
+                snap.cells,
+                (cursor_u8(snap.cursor.0), cursor_u8(snap.cursor.1)),
+                snap.mode_control,
+            ));
+        }
+
+        if !self.cfg.enable_aerogpu {
+            return None;
+        }
+        if let Some(aerogpu_mmio) = &self.aerogpu_mmio {
+            if aerogpu_mmio.borrow().scanout0_state().wddm_scanout_active {
+                return None;
+            }
+        }
+        if self.bios.video.vbe.current_mode.is_some()
+            || !matches!(self.bios.cached_video_mode() & 0x7F, 0x00..=0x03 | 0x07)
+        {
+            return None;
+        }
+        // As in `display_present_aerogpu_text_mode`, release the device borrow before reading the
+        // MMIO-routed text buffer.
+        let mode_control = match &self.aerogpu {
+            Some(aerogpu) => {
+                let dev = aerogpu.borrow();
+                if dev.vbe_active() {
+                    return None;
+                }
+                dev.attr_regs[0x10]
+            }
+            None => AeroGpuDevice::default_attr_regs()[0x10],
+        };
+        Some(text_screen::read_bda(&mut self.mem, mode_control))
     }
 
     /// Return the last framebuffer produced by [`Machine::display_present`].
@@ -7028,6 +9372,29 @@ impl Machine {
         self.scanout_state = state.map(SharedStateHandle::Static);
     }
 
+    /// Attach a shared region that receives every presented frame.
+    ///
+    /// While attached (and a scanout descriptor is installed), [`Machine::display_present`] copies
+    /// each newly rendered frame into the back slot of `slots` and flips it to the front of the
+    /// scanout descriptor as `SCANOUT_SOURCE_FRAME_SLOTS`. Guest-memory descriptors (legacy
+    /// text/VBE, WDDM) are not published meanwhile, since the presented frame already composes
+    /// them. Hosts can check `ScanoutStateSnapshot::is_double_buffered` to see whether the region
+    /// was large enough for two slots of the current resolution.
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+    pub fn set_scanout_frame_slots(&mut self, slots: Option<ScanoutFrameSlots>) {
+        self.scanout_frame_slots = slots;
+    }
+
+    /// The shared scanout descriptor, unless it carries presented frames instead of guest-memory
+    /// scanouts.
+    #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+    fn guest_scanout_state(&self) -> Option<&ScanoutState> {
+        if self.scanout_frame_slots.is_some() {
+            return None;
+        }
+        self.scanout_state.as_deref()
+    }
+
     /// Install an external hardware cursor descriptor that should receive AeroGPU cursor updates.
     ///
     /// When present, AeroGPU BAR0 cursor register updates publish updates to this descriptor so an
@@ -7084,6 +9451,42 @@ impl Machine {
         Some(cfg.bar_range(bar)?.base)
     }
 
+    /// Enumerate the PCI functions currently present on the guest-visible bus.
+    ///
+    /// The data is read live from config space (see [`PciDeviceInfo`]), so it reflects BAR
+    /// assignments, command bits and MSI/MSI-X enables programmed by firmware or the guest.
+    /// Returns an empty list if the PC platform is disabled.
+    pub fn pci_devices(&self) -> Vec<PciDeviceInfo> {
+        let Some(pci_cfg) = self.pci_config_ports() else {
+            return Vec::new();
+        };
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        pci_info::collect(pci_cfg.bus_mut())
+    }
+
+    /// Debug helper: read PCI config space without going through guest port I/O.
+    ///
+    /// Follows the config bus semantics (absent functions read as all ones, invalid sizes or
+    /// out-of-range accesses read as 0). Returns `None` if the PC platform is disabled.
+    pub fn pci_read_config(&self, bdf: PciBdf, offset: u16, size: u8) -> Option<u32> {
+        let pci_cfg = self.pci_config_ports()?;
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        Some(pci_cfg.bus_mut().read_config(bdf, offset, size))
+    }
+
+    /// Debug helper: write PCI config space without going through guest port I/O.
+    ///
+    /// Side effects (BAR relocation, decode enables, MSI/MSI-X programming) are the same as for a
+    /// guest write. This is a no-op if the PC platform is disabled.
+    pub fn pci_write_config(&mut self, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+        if let Some(pci_cfg) = self.pci_config_ports() {
+            pci_cfg
+                .borrow_mut()
+                .bus_mut()
+                .write_config(bdf, offset, size, value);
+        }
+    }
+
     /// Returns the canonical AeroGPU PCI function BDF if the device is present.
     ///
     /// The canonical AeroGPU identity contract reserves `00:07.0` for
@@ -7124,6 +9527,7 @@ impl Machine {
             return Err(MachineError::AeroGpuNotEnabled);
         };
         dev.borrow_mut().set_backend(backend);
+        self.refresh_paravirt_info();
         Ok(())
     }
 
@@ -7138,6 +9542,23 @@ impl Machine {
         mmio.borrow_mut().set_backend(Box::new(
             aero_devices_gpu::backend::ImmediateAeroGpuBackend::new(),
         ));
+        self.refresh_paravirt_info();
+    }
+
+    /// Install the CPU software AeroGPU backend (headless validation).
+    ///
+    /// The software backend executes the 2D subset of the command stream (uploads, copies, clears
+    /// and `PRESENT`) and completes fences synchronously, so presented frames can be inspected via
+    /// [`Machine::display_present`] without a GPU. This is safe to call even when AeroGPU is not
+    /// enabled/present; it will no-op.
+    pub fn aerogpu_set_backend_software(&mut self) {
+        let Some(mmio) = &self.aerogpu_mmio else {
+            return;
+        };
+        mmio.borrow_mut().set_backend(Box::new(
+            aero_devices_gpu::software::SoftwareAeroGpuBackend::new(),
+        ));
+        self.refresh_paravirt_info();
     }
 
     /// Install the "null" AeroGPU backend (drops all submissions).
@@ -7151,6 +9572,57 @@ impl Machine {
         mmio.borrow_mut().set_backend(Box::new(
             aero_devices_gpu::backend::NullAeroGpuBackend::new(),
         ));
+        self.refresh_paravirt_info();
+    }
+
+    /// Change the AeroGPU vblank rate at runtime, in millihertz (e.g. `144_000` for 144Hz).
+    ///
+    /// Lets hosts follow the display refresh rate (or throttle background tabs) without resetting
+    /// the guest. The rate is clamped to
+    /// [`AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ`]..=[`AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ`]. The new
+    /// `SCANOUT0_VBLANK_PERIOD_NS` takes effect after the already-scheduled vblank edge, and the
+    /// guest is notified via `AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED`. The rate is preserved
+    /// across [`Machine::reset`] and in snapshots.
+    pub fn aerogpu_set_vblank_rate(&mut self, millihertz: u32) -> Result<(), MachineError> {
+        let Some(dev) = &self.aerogpu_mmio else {
+            return Err(MachineError::AeroGpuNotEnabled);
+        };
+        let millihertz = millihertz.clamp(
+            AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ,
+            AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ,
+        );
+        let period_ns = aero_devices_gpu::vblank::period_ns_from_millihertz(millihertz)
+            .expect("clamped rate is non-zero");
+        dev.borrow_mut().set_vblank_period_ns(period_ns);
+        Ok(())
+    }
+
+    /// Enable or disable strict AeroGPU submission validation (a host debug option; permissive by
+    /// default).
+    ///
+    /// In strict mode each ring submission's command stream header, packet sizes, alloc-table
+    /// references and fence ordering are checked before execution. A malformed submission latches
+    /// a class-specific `AEROGPU_ERROR_*` code in BAR0, raises `AEROGPU_IRQ_ERROR`, is consumed
+    /// without completing its fence, and is recorded for
+    /// [`Machine::aerogpu_take_validation_failures`]. The setting survives [`Machine::reset`] and
+    /// is not snapshotted.
+    pub fn aerogpu_set_strict_validation(&mut self, enabled: bool) -> Result<(), MachineError> {
+        let Some(dev) = &self.aerogpu_mmio else {
+            return Err(MachineError::AeroGpuNotEnabled);
+        };
+        dev.borrow_mut().set_strict_validation(enabled);
+        Ok(())
+    }
+
+    /// Drain the submissions rejected by strict AeroGPU validation since the previous call, oldest
+    /// first. At most [`AEROGPU_VALIDATION_FAILURE_CAP`] are retained.
+    ///
+    /// Returns an empty list when AeroGPU is not present.
+    pub fn aerogpu_take_validation_failures(&mut self) -> Vec<AeroGpuValidationFailure> {
+        match &self.aerogpu_mmio {
+            Some(dev) => dev.borrow_mut().take_validation_failures(),
+            None => Vec::new(),
+        }
     }
 
     /// Install the native wgpu-based AeroGPU backend (headless) if available.
@@ -7167,6 +9639,7 @@ impl Machine {
         let backend = aero_devices_gpu::backend::NativeAeroGpuBackend::new_headless()
             .map_err(|err| err.to_string())?;
         mmio.borrow_mut().set_backend(Box::new(backend));
+        self.refresh_paravirt_info();
         Ok(())
     }
 
@@ -7187,16 +9660,94 @@ impl Machine {
         self.pci_intx.clone()
     }
 
+    /// Attach an ISA device to 8237 DMA `channel` (0-3 8-bit, 5-7 16-bit).
+    ///
+    /// The client's DREQ is polled from `run_slice` and transfers follow the mode the guest
+    /// programmed. Registrations are host wiring: they survive reset and snapshot restore. The
+    /// built-in floppy controller drives channel 2 directly and does not register a client.
+    pub fn register_isa_dma_client(
+        &mut self,
+        channel: usize,
+        client: SharedDmaChannelClient,
+    ) -> Result<(), MachineError> {
+        let dma = self
+            .dma
+            .as_ref()
+            .ok_or(MachineError::IsaDmaRequiresPcPlatform)?;
+        dma.borrow_mut()
+            .register_client(channel, client)
+            .map_err(MachineError::IsaDmaClient)
+    }
+
     /// Returns the PIT 8254 device, if present.
     pub fn pit(&self) -> Option<SharedPit8254> {
         self.pit.clone()
     }
 
+    /// Drain PC speaker tones (PIT channel 2 gated via port 0x61) completed since the last call.
+    ///
+    /// Event times are in guest nanoseconds since reset, on the same timebase as
+    /// [`Machine::platform_clock`], so a host can schedule the tones (e.g. via WebAudio) without
+    /// depending on wall-clock execution speed. Returns an empty list if the PC platform is
+    /// disabled.
+    pub fn take_speaker_events(&mut self) -> Vec<SpeakerEvent> {
+        self.pit
+            .as_ref()
+            .map(|pit| pit.borrow_mut().take_speaker_events())
+            .unwrap_or_default()
+    }
+
     /// Returns the RTC CMOS device, if present.
     pub fn rtc(&self) -> Option<SharedRtcCmos<ManualClock, PlatformIrqLine>> {
         self.rtc.clone()
     }
 
+    /// Serialize the machine's non-volatile state (currently the RTC CMOS RAM bank,
+    /// `0x10..=0x7F`) so the host can persist it next to the disk image.
+    ///
+    /// Feed the blob back through [`MachineConfig::initial_nvram`] or [`Machine::import_nvram`].
+    pub fn export_nvram(&self) -> Vec<u8> {
+        nvram::NvramImage {
+            rtc_cmos: self
+                .rtc
+                .as_ref()
+                .map(|rtc| rtc.borrow().nvram_bank())
+                .or(self.pending_cmos_nvram),
+        }
+        .encode()
+    }
+
+    /// Replace the machine's non-volatile state with a blob from [`Machine::export_nvram`].
+    ///
+    /// Takes effect immediately and, like [`MachineConfig::initial_nvram`], is restored by later
+    /// cold resets. Firmware-owned CMOS fields are re-derived from the machine configuration.
+    /// Stores missing from the blob are left untouched.
+    pub fn import_nvram(&mut self, blob: &[u8]) -> Result<(), MachineError> {
+        let image = nvram::NvramImage::decode(blob).map_err(MachineError::InvalidNvram)?;
+        if let Some(bank) = image.rtc_cmos {
+            self.pending_cmos_nvram = Some(bank);
+            if let Some(rtc) = self.rtc.clone() {
+                let mut rtc = rtc.borrow_mut();
+                rtc.load_nvram_bank(&bank);
+                self.apply_firmware_cmos_fields(&mut rtc);
+            }
+        }
+        Ok(())
+    }
+
+    /// Overwrite the CMOS fields the firmware owns (memory sizes, floppy/equipment bytes) and
+    /// recompute the CMOS checksum, leaving guest-owned bytes intact.
+    fn apply_firmware_cmos_fields(&self, rtc: &mut RtcCmos<ManualClock, PlatformIrqLine>) {
+        rtc.set_memory_size_bytes(self.cfg.ram_size_bytes);
+        let floppy_type = if self.floppy.is_some() {
+            CMOS_FLOPPY_TYPE_1_44M
+        } else {
+            0
+        };
+        rtc.set_floppy_drive_types(floppy_type, 0);
+        rtc.update_checksum();
+    }
+
     /// Returns the ACPI PM I/O device, if present.
     pub fn acpi_pm(&self) -> Option<SharedAcpiPmIo<ManualClock>> {
         self.acpi_pm.clone()
@@ -7213,9 +9764,110 @@ impl Machine {
         let mut pm = acpi_pm.borrow_mut();
         pm.set_wake_status();
         pm.trigger_power_button();
+        if self.power_state == GuestPowerState::Sleeping {
+            self.power_state = GuestPowerState::Running;
+        }
+    }
+
+    /// Press the ACPI fixed-feature power button.
+    ///
+    /// This latches `PM1_STS.PWRBTN_STS` and raises an SCI once the guest has enabled the event,
+    /// which ACPI OSes (Windows included) treat as a request for an orderly shutdown. The FADT
+    /// advertises the power button as a fixed feature, so no AML is involved. Completion is
+    /// observable via [`Machine::power_state`] and [`RunExit::PowerOff`].
+    ///
+    /// Returns `false` if the machine has no ACPI PM device (i.e. the PC platform is disabled).
+    pub fn press_power_button(&mut self) -> bool {
+        let Some(acpi_pm) = &self.acpi_pm else {
+            return false;
+        };
+        acpi_pm.borrow_mut().trigger_power_button();
+        true
+    }
+
+    /// Returns the guest power state derived from its ACPI sleep requests (`PM1a_CNT` writes).
+    ///
+    /// The state returns to [`GuestPowerState::Running`] on every reset; it is not snapshotted.
+    pub fn power_state(&self) -> GuestPowerState {
+        self.power_state_with_pending_request()
+    }
+
+    fn power_state_with_pending_request(&self) -> GuestPowerState {
+        match self.acpi_sleep_latch.get() {
+            Some(state) => Self::power_state_for_sleep(state).unwrap_or(self.power_state),
+            None => self.power_state,
+        }
+    }
+
+    fn power_state_for_sleep(state: AcpiSleepState) -> Option<GuestPowerState> {
+        match state {
+            AcpiSleepState::S1 | AcpiSleepState::S2 | AcpiSleepState::S3 => {
+                Some(GuestPowerState::Sleeping)
+            }
+            // S4 ends in a powered-off machine in a VM (see `aero_devices::acpi_pm`).
+            AcpiSleepState::S4 | AcpiSleepState::S5 => Some(GuestPowerState::SoftOff),
+            AcpiSleepState::Other(_) => None,
+        }
+    }
+
+    /// Fold a latched ACPI sleep request into `power_state` and report whether the machine is off.
+    fn poll_power_off(&mut self) -> bool {
+        self.power_state = self.power_state_with_pending_request();
+        self.acpi_sleep_latch.set(None);
+        self.power_state == GuestPowerState::SoftOff
+    }
+
+    /// Set the guest RTC/CMOS clock to the host wall-clock time `now_unix_ms` (milliseconds since
+    /// the Unix epoch, e.g. `Date.now()`).
+    ///
+    /// Hosts call this after restoring a snapshot (or after a long pause) so the guest's calendar
+    /// time does not resume from the moment the snapshot was taken. Monotonic guest time (the
+    /// platform clock, TSC, PM timer and HPET) is left untouched so relative timers keep running;
+    /// only the RTC's wall-clock offset changes (see [`RtcCmos::set_wall_clock_unix_ms`]). With
+    /// [`MachineConfig::acpi_rtc_resync_event`] the ACPI fixed RTC event is raised as well.
+    pub fn resync_wall_clock(&mut self, now_unix_ms: i64) {
+        if let Some(rtc) = &self.rtc {
+            rtc.borrow_mut().set_wall_clock_unix_ms(now_unix_ms);
+        }
+        if self.cfg.acpi_rtc_resync_event {
+            if let Some(acpi_pm) = &self.acpi_pm {
+                acpi_pm.borrow_mut().trigger_pm1_event(PM1_STS_RTC);
+            }
+        }
+    }
+
+    /// [`Machine::resync_wall_clock`] using the host's current system time.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn resync_wall_clock_to_host(&mut self) {
+        let now_unix_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        self.resync_wall_clock(now_unix_ms);
+    }
+
+    /// Returns the TPM 2.0 CRB device, if present.
+    pub fn tpm(&self) -> Option<Rc<RefCell<TpmCrb>>> {
+        self.tpm.clone()
+    }
+
+    /// Install the command backend for the TPM device.
+    ///
+    /// The backend persists across guest resets (which only send it `_TPM_Init` via
+    /// [`TpmBackend::reset`]) and its state is captured in snapshots through
+    /// [`TpmBackend::save_state`]. Returns `false` if no TPM device is present.
+    pub fn set_tpm_backend(&mut self, backend: Box<dyn TpmBackend>) -> bool {
+        let Some(tpm) = &self.tpm else {
+            return false;
+        };
+        tpm.borrow_mut().set_backend(backend);
+        true
     }
 
     /// Returns the HPET device, if present.
+    ///
+    /// With device tick scheduling (see [`Machine::set_device_tick_scheduling`]) the HPET is only
+    /// polled at its next comparator match or after a guest MMIO write. Host code that reprograms
+    /// it through this handle should disable scheduling, or set it again to force a poll.
     pub fn hpet(&self) -> Option<Rc<RefCell<hpet::Hpet<ManualClock>>>> {
         self.hpet.clone()
     }
@@ -7235,6 +9887,24 @@ impl Machine {
         self.e1000.clone()
     }
 
+    /// Plug or unplug the (virtual) network cable of the E1000 NIC.
+    ///
+    /// The NIC reports the new state in `STATUS.LU` and its PHY link registers, and raises a Link
+    /// Status Change interrupt if the state changed.
+    ///
+    /// This is host configuration: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_nic_link_up(&mut self, up: bool) {
+        self.nic_link_up = up;
+        if let Some(e1000) = &self.e1000 {
+            e1000.borrow_mut().set_link_up(up);
+        }
+    }
+
+    /// Return the cable state set by [`Machine::set_nic_link_up`] (defaults to up).
+    pub fn nic_link_up(&self) -> bool {
+        self.nic_link_up
+    }
+
     /// Returns the virtio-net (virtio-pci) device, if present.
     pub fn virtio_net(&self) -> Option<Rc<RefCell<VirtioPciDevice>>> {
         self.virtio_net.clone()
@@ -7254,6 +9924,22 @@ impl Machine {
     pub fn virtio_input_tablet(&self) -> Option<Rc<RefCell<VirtioPciDevice>>> {
         self.virtio_input_tablet.clone()
     }
+
+    /// Returns the virtio-balloon (virtio-pci) device, if present.
+    pub fn virtio_balloon(&self) -> Option<Rc<RefCell<VirtioPciDevice>>> {
+        self.virtio_balloon.clone()
+    }
+
+    /// Returns the virtio-9p (virtio-pci) device, if present.
+    pub fn virtio_9p(&self) -> Option<Rc<RefCell<VirtioPciDevice>>> {
+        self.virtio_9p.clone()
+    }
+
+    /// Returns the virtio-rng (virtio-pci) device, if present.
+    pub fn virtio_rng(&self) -> Option<Rc<RefCell<VirtioPciDevice>>> {
+        self.virtio_rng.clone()
+    }
+
     /// Returns the VGA/SVGA device, if present.
     pub fn vga(&self) -> Option<Rc<RefCell<VgaDevice>>> {
         self.vga.clone()
@@ -7294,6 +9980,28 @@ impl Machine {
         (base != 0).then_some(base)
     }
 
+    /// Describe the AeroGPU BAR1 VRAM backing store so a host-side GPU worker can access it
+    /// directly instead of copying allocations out through [`Machine::read_physical`].
+    ///
+    /// Returns `None` if AeroGPU is disabled. See [`VramExportDescriptor`] for the access contract;
+    /// callers should compare [`VramExportDescriptor::generation`] against
+    /// [`Machine::aerogpu_vram_generation`] before each use.
+    pub fn aerogpu_vram_export(&self) -> Option<VramExportDescriptor> {
+        let dev = self.aerogpu.as_ref()?;
+        let mut dev = dev.borrow_mut();
+        Some(VramExportDescriptor {
+            ptr: dev.vram.as_mut_ptr(),
+            len: dev.vram.len(),
+            bar1_base: self.aerogpu_vram_bar_base(),
+            generation: self.aerogpu_vram_generation,
+        })
+    }
+
+    /// Current AeroGPU VRAM generation (see [`VramExportDescriptor::generation`]).
+    pub fn aerogpu_vram_generation(&self) -> u64 {
+        self.aerogpu_vram_generation
+    }
+
     /// Returns the PIIX3-compatible IDE controller, if present.
     pub fn ide(&self) -> Option<Rc<RefCell<Piix3IdePciDevice>>> {
         self.ide.clone()
@@ -7303,6 +10011,16 @@ impl Machine {
         self.virtio_blk.clone()
     }
 
+    fn virtio_blk_options(&self) -> VirtioBlkOptions {
+        match self.cfg.virtio_blk_num_queues {
+            Some(num_queues) => VirtioBlkOptions {
+                num_queues,
+                backend_limits: true,
+            },
+            None => VirtioBlkOptions::default(),
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     fn swap_virtio_blk_backend_preserving_state(
         &mut self,
@@ -7367,13 +10085,17 @@ impl Machine {
             Some(ints) => Box::new(VirtioMsixInterruptSink::new(ints.clone())),
             None => Box::new(NoopVirtioInterruptSink),
         };
-        let mut new_dev = VirtioPciDevice::new(Box::new(VirtioBlk::new(disk)), interrupt_sink);
+        let mut new_dev = self.cfg.virtio_blk_transport.new_device(
+            Box::new(VirtioBlk::with_options(disk, self.virtio_blk_options())),
+            interrupt_sink,
+        );
         new_dev.load_state(&state).map_err(|e| {
             MachineError::DiskBackend(format!(
                 "failed to restore virtio-blk state after attaching disk backend: {e}"
             ))
         })?;
         *virtio_blk.borrow_mut() = new_dev;
+        self.install_storage_fault_injectors(false);
 
         // Keep the device model's internal PCI config view coherent with the canonical PCI config
         // space. This ensures `save_state()` sees consistent BAR programming without requiring an
@@ -7460,7 +10182,11 @@ impl Machine {
         let Some(xhci) = &self.xhci else {
             return Ok(());
         };
-        xhci.borrow_mut().controller_mut().detach_at_path(path)
+        xhci.borrow_mut().controller_mut().detach_at_path(path)?;
+        if let [root_port] = path {
+            self.xhci_attached_ports.remove(root_port);
+        }
+        Ok(())
     }
 
     /// Attach a USB device model directly to an xHCI root hub port.
@@ -7487,6 +10213,62 @@ impl Machine {
         self.usb_xhci_detach_at_path(&path)
     }
 
+    /// Attach a host-provided USB device (e.g. WebUSB passthrough) directly to an xHCI root port.
+    ///
+    /// `port` is 0-based. The machine's xHCI exposes [`Machine::XHCI_USB2_PORT_COUNT`] USB2 ports
+    /// followed by [`Machine::XHCI_USB3_PORT_COUNT`] USB3 ports starting at
+    /// [`Machine::XHCI_FIRST_USB3_PORT`]. SuperSpeed devices may use either kind (falling back to
+    /// high-speed on USB2 ports); slower devices must use a USB2 port.
+    ///
+    /// The device reports its own speed via [`aero_usb::UsbDeviceModel::speed`]; this is what the
+    /// guest sees in PORTSC and in the Slot Context. The port raises a port status change event
+    /// once the link is up (immediately for USB2 ports, after link training for USB3 ports).
+    ///
+    /// Unlike [`Machine::usb_xhci_attach_root`], this returns
+    /// [`aero_usb::UsbHubAttachError::InvalidPort`] when xHCI is not enabled, since the caller is
+    /// handing over a live device that would otherwise be silently dropped.
+    ///
+    /// The port is recorded in [`Machine::xhci_attached_ports`] so hosts know what to re-attach
+    /// after restoring a snapshot.
+    pub fn xhci_attach_device(
+        &mut self,
+        port: u8,
+        device: Box<dyn aero_usb::UsbDeviceModel>,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        let Some(xhci) = &self.xhci else {
+            return Err(aero_usb::UsbHubAttachError::InvalidPort);
+        };
+        xhci.borrow_mut()
+            .controller_mut()
+            .attach_at_path(&[port], device)?;
+        self.xhci_attached_ports.insert(port);
+        Ok(())
+    }
+
+    /// Detach a device previously attached with [`Machine::xhci_attach_device`].
+    ///
+    /// `port` is 0-based. The guest observes a disconnect (CCS cleared, CSC set) and a port
+    /// status change event.
+    pub fn xhci_detach_device(&mut self, port: u8) -> Result<(), aero_usb::UsbHubAttachError> {
+        let Some(xhci) = &self.xhci else {
+            return Err(aero_usb::UsbHubAttachError::InvalidPort);
+        };
+        xhci.borrow_mut().controller_mut().detach_at_path(&[port])?;
+        self.xhci_attached_ports.remove(&port);
+        Ok(())
+    }
+
+    /// xHCI root ports (0-based) that currently hold a device attached via
+    /// [`Machine::xhci_attach_device`], in ascending order.
+    ///
+    /// Like UHCI passthrough, host-provided device models are not part of machine snapshots.
+    /// After [`Machine::restore_snapshot_bytes`] this list reflects the snapshot's attachment
+    /// points; the host is expected to re-attach its devices there, detaching first since the
+    /// restored controller may hold a backend-less placeholder model on those ports.
+    pub fn xhci_attached_ports(&self) -> Vec<u8> {
+        self.xhci_attached_ports.iter().copied().collect()
+    }
+
     /// Attach a USB device model to a UHCI root hub port.
     ///
     /// `port` is 0-based (UHCI exposes two root ports: `0` and `1`).
@@ -7764,32 +10546,279 @@ impl Machine {
             .hub_mut()
             .detach_at_path(path)
     }
-    /// Attach an ATA drive to the canonical AHCI port 0, if the AHCI controller is enabled.
-    pub fn attach_ahci_drive_port0(&mut self, drive: AtaDrive) {
-        self.attach_ahci_drive(0, drive);
-    }
 
-    /// Attach an ATA drive to an AHCI port, if present.
-    pub fn attach_ahci_drive(&mut self, port: usize, drive: AtaDrive) {
-        let Some(ahci) = &self.ahci else {
-            return;
-        };
-        if port == 0 {
-            // The host is explicitly attaching a drive backend. Do not auto-attach/overwrite this
-            // slot with the machine's `SharedDisk` on future resets or `set_disk_image()` calls.
-            self.ahci_port0_auto_attach_shared_disk = false;
+    /// Number of physical USB 2.0 ports exposed by the machine.
+    ///
+    /// Physical ports are the host-facing attach points shared by the USB 1.1/2.0 controllers:
+    ///
+    /// - With EHCI enabled, physical port `n` is EHCI root port `n`. When UHCI is also enabled,
+    ///   ports `0` and `1` are shared with the companion UHCI controller through a
+    ///   CONFIGFLAG/PORT_OWNER mux, so a device appears on exactly one controller at a time.
+    /// - With only UHCI enabled, physical port `n` is UHCI root port `n`.
+    ///
+    /// Returns 0 if neither controller is enabled.
+    pub fn usb_physical_port_count(&self) -> usize {
+        if let Some(ehci) = &self.ehci {
+            return ehci.borrow().controller().hub().num_ports();
         }
-        ahci.borrow_mut().attach_drive(port, drive);
+        if self.uhci.is_some() {
+            // UHCI root hub has 2 ports (PORTSC1/PORTSC2).
+            return 2;
+        }
+        0
     }
 
-    /// Attach a disk image to the canonical AHCI port 0, if the AHCI controller is enabled.
-    pub fn attach_ahci_disk_port0(
+    /// Attach a USB device model to a physical USB 2.0 port.
+    ///
+    /// See [`Machine::usb_physical_port_count`] for the port numbering. The controller that
+    /// enumerates the device is chosen by the guest (via EHCI CONFIGFLAG/PORT_OWNER), not by the
+    /// host: full/low-speed devices on shared ports are handed to UHCI by the EHCI driver.
+    ///
+    /// If neither UHCI nor EHCI is enabled on this machine, this is a no-op and returns `Ok(())`.
+    pub fn usb_attach_physical_port(
         &mut self,
-        disk: Box<dyn aero_storage::VirtualDisk>,
-    ) -> std::io::Result<()> {
-        self.attach_ahci_drive_port0(AtaDrive::new(disk)?);
-        Ok(())
-    }
+        port: u8,
+        model: Box<dyn aero_usb::UsbDeviceModel>,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        let path = [port];
+        self.usb_attach_physical_path(&path, model)
+    }
+
+    /// Detach any USB device model from a physical USB 2.0 port.
+    ///
+    /// The disconnect is reported to whichever controller currently owns the port.
+    ///
+    /// If neither UHCI nor EHCI is enabled on this machine, this is a no-op and returns `Ok(())`.
+    pub fn usb_detach_physical_port(
+        &mut self,
+        port: u8,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        let path = [port];
+        self.usb_detach_physical_path(&path)
+    }
+
+    /// Attach a USB device model at a topology path rooted at a physical USB 2.0 port.
+    ///
+    /// `path[0]` is the physical port index (0-based); `path[1..]` are downstream hub port numbers
+    /// (1-based).
+    pub fn usb_attach_physical_path(
+        &mut self,
+        path: &[u8],
+        model: Box<dyn aero_usb::UsbDeviceModel>,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        if self.ehci.is_some() {
+            self.usb_ehci_attach_at_path(path, model)
+        } else {
+            self.usb_attach_at_path(path, model)
+        }
+    }
+
+    /// Detach any USB device model at a topology path rooted at a physical USB 2.0 port.
+    pub fn usb_detach_physical_path(
+        &mut self,
+        path: &[u8],
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        if self.ehci.is_some() {
+            self.usb_ehci_detach_at_path(path)
+        } else {
+            self.usb_detach_at_path(path)
+        }
+    }
+
+    /// Returns which controller currently owns a physical USB 2.0 port.
+    ///
+    /// Shared ports report the live CONFIGFLAG/PORT_OWNER routing decision; EHCI-only ports always
+    /// report [`Usb2PortOwner::Ehci`] and UHCI-only machines always report
+    /// [`Usb2PortOwner::Companion`]. Returns `None` for out-of-range ports.
+    pub fn usb_physical_port_owner(&self, port: u8) -> Option<Usb2PortOwner> {
+        let port = usize::from(port);
+        if port >= self.usb_physical_port_count() {
+            return None;
+        }
+        if let Some(mux) = &self.usb2_mux {
+            if let Some(owner) = mux.borrow().port_owner(port) {
+                return Some(owner);
+            }
+        }
+        if self.ehci.is_some() {
+            Some(Usb2PortOwner::Ehci)
+        } else {
+            Some(Usb2PortOwner::Companion)
+        }
+    }
+
+    /// Attach the WebUSB passthrough device to its reserved physical USB 2.0 port
+    /// ([`Machine::UHCI_WEBUSB_ROOT_PORT`]).
+    ///
+    /// With EHCI enabled the port is shared with UHCI, so the guest decides which controller
+    /// enumerates the device. If neither UHCI nor EHCI is enabled, this is a no-op and returns
+    /// `Ok(())`.
+    pub fn usb_attach_webusb_passthrough(
+        &mut self,
+        device: aero_usb::UsbWebUsbPassthroughDevice,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        self.usb_attach_physical_port(Self::UHCI_WEBUSB_ROOT_PORT, Box::new(device))
+    }
+
+    /// Detach the WebUSB passthrough device from its reserved physical USB 2.0 port.
+    ///
+    /// The disconnect is reported to whichever controller currently owns the port.
+    pub fn usb_detach_webusb_passthrough(&mut self) -> Result<(), aero_usb::UsbHubAttachError> {
+        self.usb_detach_physical_port(Self::UHCI_WEBUSB_ROOT_PORT)
+    }
+
+    /// Attach a host-proxied USB device to a physical USB 2.0 port.
+    ///
+    /// The device answers standard descriptor requests from `descriptors`; every other
+    /// control/bulk/interrupt transfer is queued for the host (see
+    /// [`Machine::usb_proxy_take_requests`]) and NAKed until the host completes it with
+    /// [`Machine::usb_proxy_complete`], so `run_slice` never blocks on the host.
+    ///
+    /// Returns [`aero_usb::UsbHubAttachError::InvalidPort`] if `port` is not a physical port of
+    /// this machine (including machines without UHCI/EHCI).
+    pub fn usb_proxy_attach(
+        &mut self,
+        port: u8,
+        descriptors: ProxyUsbDescriptors,
+    ) -> Result<ProxyHandle, aero_usb::UsbHubAttachError> {
+        if usize::from(port) >= self.usb_physical_port_count() {
+            return Err(aero_usb::UsbHubAttachError::InvalidPort);
+        }
+        let device = ProxyUsbDevice::new(descriptors);
+        self.usb_attach_physical_port(port, Box::new(device.clone()))?;
+
+        let id = self.next_usb_proxy_id;
+        self.next_usb_proxy_id = self.next_usb_proxy_id.wrapping_add(1).max(1);
+        self.usb_proxies
+            .insert(id, MachineUsbProxy { port, device });
+        Ok(ProxyHandle(id))
+    }
+
+    /// Drain guest-initiated transfers queued by a proxied USB device.
+    ///
+    /// Returns an empty list for unknown handles.
+    pub fn usb_proxy_take_requests(&mut self, handle: ProxyHandle) -> Vec<UsbHostAction> {
+        self.usb_proxies
+            .get(&handle.0)
+            .map(|proxy| proxy.device.take_requests())
+            .unwrap_or_default()
+    }
+
+    /// Complete a transfer previously returned by [`Machine::usb_proxy_take_requests`].
+    ///
+    /// Returns `false` if the handle is unknown or the request is no longer outstanding (e.g. the
+    /// guest abandoned a control transfer, or the device was reset); such completions are dropped.
+    pub fn usb_proxy_complete(
+        &mut self,
+        handle: ProxyHandle,
+        request_id: u32,
+        result: ProxyUsbResult,
+    ) -> bool {
+        self.usb_proxies
+            .get(&handle.0)
+            .is_some_and(|proxy| proxy.device.complete(request_id, result))
+    }
+
+    /// Detach a proxied USB device from its physical port.
+    ///
+    /// Outstanding requests are stalled so no guest transfer is left waiting on the host, and
+    /// later completions for them are ignored.
+    pub fn usb_proxy_detach(
+        &mut self,
+        handle: ProxyHandle,
+    ) -> Result<(), aero_usb::UsbHubAttachError> {
+        let Some(proxy) = self.usb_proxies.remove(&handle.0) else {
+            return Err(aero_usb::UsbHubAttachError::NoDevice);
+        };
+        proxy.device.detach();
+        self.usb_detach_physical_port(proxy.port)
+    }
+
+    /// Currently attached proxied USB devices and their physical ports, ordered by handle.
+    ///
+    /// After restoring a snapshot, hosts should use this to re-bind each proxied device to its
+    /// backing host device: in-flight transfers are not part of snapshots, so the guest's retried
+    /// transfers will show up again in [`Machine::usb_proxy_take_requests`].
+    pub fn usb_proxy_handles(&self) -> Vec<(ProxyHandle, u8)> {
+        let mut out: Vec<_> = self
+            .usb_proxies
+            .iter()
+            .map(|(&id, proxy)| (ProxyHandle(id), proxy.port))
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// Return the descriptors registered for a proxied USB device.
+    pub fn usb_proxy_descriptors(&self, handle: ProxyHandle) -> Option<ProxyUsbDescriptors> {
+        self.usb_proxies
+            .get(&handle.0)
+            .map(|proxy| proxy.device.descriptors())
+    }
+
+    /// Runs `f` on the device attached directly to physical USB 2.0 port `port`, on whichever
+    /// root hub backs it (see [`Machine::usb_physical_port_count`]). Returns `None` if the port is
+    /// empty or out of range.
+    fn with_usb_physical_port_device_mut<R>(
+        ehci: Option<&Rc<RefCell<EhciPciDevice>>>,
+        uhci: Option<&Rc<RefCell<UhciPciDevice>>>,
+        port: u8,
+        f: impl FnOnce(&mut aero_usb::device::AttachedUsbDevice) -> R,
+    ) -> Option<R> {
+        let port = usize::from(port);
+        if let Some(ehci) = ehci {
+            let mut ehci = ehci.borrow_mut();
+            let mut dev = ehci.controller_mut().hub_mut().port_device_mut(port)?;
+            return Some(f(&mut dev));
+        }
+        let mut uhci = uhci?.borrow_mut();
+        let mut dev = uhci.controller_mut().hub_mut().port_device_mut(port)?;
+        Some(f(&mut dev))
+    }
+
+    fn usb_physical_port_proxy_device(&self, port: u8) -> Option<ProxyUsbDevice> {
+        let port = usize::from(port);
+        let find = |dev: &aero_usb::device::AttachedUsbDevice| {
+            (dev.model() as &dyn Any)
+                .downcast_ref::<ProxyUsbDevice>()
+                .cloned()
+        };
+        if let Some(ehci) = &self.ehci {
+            let ehci = ehci.borrow();
+            let dev = ehci.controller().hub().port_device(port)?;
+            return find(&dev);
+        }
+        let uhci = self.uhci.as_ref()?.borrow();
+        let dev = uhci.controller().hub().port_device(port)?;
+        find(&dev)
+    }
+
+    /// Attach an ATA drive to the canonical AHCI port 0, if the AHCI controller is enabled.
+    pub fn attach_ahci_drive_port0(&mut self, drive: AtaDrive) {
+        self.attach_ahci_drive(0, drive);
+    }
+
+    /// Attach an ATA drive to an AHCI port, if present.
+    pub fn attach_ahci_drive(&mut self, port: usize, drive: AtaDrive) {
+        let Some(ahci) = &self.ahci else {
+            return;
+        };
+        if port == 0 {
+            // The host is explicitly attaching a drive backend. Do not auto-attach/overwrite this
+            // slot with the machine's `SharedDisk` on future resets or `set_disk_image()` calls.
+            self.ahci_port0_auto_attach_shared_disk = false;
+        }
+        ahci.borrow_mut().attach_drive(port, drive);
+    }
+
+    /// Attach a disk image to the canonical AHCI port 0, if the AHCI controller is enabled.
+    pub fn attach_ahci_disk_port0(
+        &mut self,
+        disk: Box<dyn aero_storage::VirtualDisk>,
+    ) -> std::io::Result<()> {
+        self.attach_ahci_drive_port0(AtaDrive::new(disk)?);
+        Ok(())
+    }
 
     /// Attach a disk backend to the NVMe controller, if present.
     ///
@@ -7961,6 +10990,64 @@ impl Machine {
             .controller
             .attach_secondary_master_atapi(dev);
     }
+    /// Insert a 1.44MB floppy image into drive A: (`disk_id=3`).
+    ///
+    /// The same backend services the 82077AA floppy controller (when the PC platform is enabled)
+    /// and BIOS INT 13h accesses to drive `0x00`, so real-mode and protected-mode drivers observe
+    /// the same media. The drive is reported in the BDA equipment word and CMOS (`0x10`/`0x14`).
+    ///
+    /// Only 1.44MB images (2880 sectors) are supported.
+    pub fn attach_floppy_image(
+        &mut self,
+        disk: Box<dyn aero_storage::VirtualDisk>,
+    ) -> io::Result<()> {
+        let expected =
+            aero_devices_storage::fdc::FLOPPY_144_TOTAL_SECTORS * aero_storage::SECTOR_SIZE as u64;
+        if disk.capacity_bytes() != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "floppy image must be {expected} bytes (1.44MB), got {}",
+                    disk.capacity_bytes()
+                ),
+            ));
+        }
+
+        let shared = SharedDisk::new(disk);
+        if let Some(fdc) = &self.fdc {
+            fdc.borrow_mut().attach_media(0, Box::new(shared.clone()));
+        }
+        self.floppy = Some(shared);
+        if let Some(rtc) = &self.rtc {
+            let mut rtc = rtc.borrow_mut();
+            rtc.set_floppy_drive_types(CMOS_FLOPPY_TYPE_1_44M, 0);
+            rtc.update_checksum();
+        }
+        self.report_floppy_drive_in_bda();
+        Ok(())
+    }
+
+    /// Eject the floppy image from drive A:, if any, and drop its overlay reference.
+    ///
+    /// The drive stays visible to the guest (with no media) until the next reset.
+    pub fn eject_floppy(&mut self) {
+        self.floppy = None;
+        self.floppy_overlay = None;
+        if let Some(fdc) = &self.fdc {
+            fdc.borrow_mut().eject_media(0);
+        }
+    }
+
+    /// Mark drive A: as installed in the BDA equipment word (`0x40:0x10`) without disturbing a
+    /// drive count the firmware already advertised.
+    fn report_floppy_drive_in_bda(&mut self) {
+        let addr = firmware::bios::BDA_BASE + 0x10;
+        let equipment = self.mem.read_u16(addr);
+        if equipment & 1 == 0 {
+            self.mem.write_u16(addr, (equipment & !0xC0) | 1);
+        }
+    }
+
     /// Attach an ISO backend as the machine's canonical install media / ATAPI CD-ROM (`disk_id=1`).
     ///
     /// This models the media as inserted (updates guest-visible tray/media state) and also updates
@@ -8249,6 +11336,7 @@ impl Machine {
 
         if let Some(vga) = &self.vga {
             vga.borrow_mut().tick(delta_ns);
+            perf::bump(&mut self.perf.device_ticks.vga);
         }
 
         if let Some(clock) = &self.platform_clock {
@@ -8257,27 +11345,60 @@ impl Machine {
 
         if let Some(acpi_pm) = &self.acpi_pm {
             acpi_pm.borrow_mut().tick(delta_ns);
+            perf::bump(&mut self.perf.device_ticks.acpi_pm);
         }
 
         if let Some(pit) = &self.pit {
             pit.borrow_mut().advance_ns(delta_ns);
+            perf::bump(&mut self.perf.device_ticks.pit);
         }
 
         if let Some(rtc) = &self.rtc {
             rtc.borrow_mut().tick();
+            perf::bump(&mut self.perf.device_ticks.rtc);
         }
 
         if let Some(interrupts) = &self.interrupts {
             interrupts.borrow().tick(delta_ns);
+            perf::bump(&mut self.perf.device_ticks.lapic);
         }
 
         if let (Some(hpet), Some(interrupts)) = (&self.hpet, &self.interrupts) {
-            let mut hpet = hpet.borrow_mut();
-            let mut interrupts = interrupts.borrow_mut();
-            hpet.poll(&mut *interrupts);
+            // Between comparator matches a poll only advances the main counter, which the HPET
+            // also catches up on every MMIO access. Park it until the next match or guest write.
+            let now_ns = self
+                .platform_clock
+                .as_ref()
+                .map_or(0, |clock| clock.now_ns());
+            let doorbell = self.mmio_perf.hpet.writes();
+            if self
+                .tick_scheduler
+                .parked_due(TickDevice::Hpet, now_ns, doorbell)
+            {
+                let mut hpet = hpet.borrow_mut();
+                let mut interrupts = interrupts.borrow_mut();
+                hpet.poll(&mut *interrupts);
+                perf::bump(&mut self.perf.device_ticks.hpet);
+                let deadline_ns = hpet
+                    .ns_until_next_interrupt()
+                    .map(|ns| now_ns.saturating_add(ns));
+                self.tick_scheduler
+                    .park(TickDevice::Hpet, deadline_ns, doorbell);
+            } else {
+                perf::bump(&mut self.perf.skipped_device_ticks.hpet);
+            }
         }
 
-        if let Some(aerogpu_mmio) = self.aerogpu_mmio.as_ref() {
+        let aerogpu_idle = self
+            .aerogpu_mmio
+            .as_ref()
+            .is_some_and(|dev| dev.borrow().vblank_idle());
+        if self
+            .tick_scheduler
+            .skip_idle(TickDevice::AeroGpu, aerogpu_idle)
+        {
+            perf::bump(&mut self.perf.skipped_device_ticks.aerogpu);
+        } else if let Some(aerogpu_mmio) = self.aerogpu_mmio.as_ref() {
             // Keep the AeroGPU model's internal PCI config image coherent with the canonical PCI
             // config space before ticking. This ensures bus mastering gating applies even when the
             // guest toggles COMMAND.BME between `tick_platform` calls (without an intervening
@@ -8311,12 +11432,17 @@ impl Machine {
                     aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
                     bar1_base,
                 );
-                dev.tick(delta_ns, &mut self.mem);
+                dev.tick(delta_ns, &mut self.mem.bus_master());
             } else {
-                aerogpu_mmio.borrow_mut().tick(delta_ns, &mut self.mem);
+                aerogpu_mmio
+                    .borrow_mut()
+                    .tick(delta_ns, &mut self.mem.bus_master());
             }
+            perf::bump(&mut self.perf.device_ticks.aerogpu);
         }
 
+        // A halted controller with quiet root ports ignores its frame ticks, so skip them. The PCI
+        // config mirror below is cheap and does not touch guest RAM, so it always runs.
         if let Some(uhci) = self.uhci.as_ref() {
             const NS_PER_MS: u64 = 1_000_000;
 
@@ -8344,13 +11470,23 @@ impl Machine {
                     .set_bar_base(UhciPciDevice::IO_BAR_INDEX, bar4_base);
             }
 
-            self.uhci_ns_remainder = self.uhci_ns_remainder.saturating_add(delta_ns);
-            let mut ticks = self.uhci_ns_remainder / NS_PER_MS;
-            self.uhci_ns_remainder %= NS_PER_MS;
-
-            while ticks != 0 {
-                uhci.tick_1ms(&mut self.mem.bus);
-                ticks -= 1;
+            if self
+                .tick_scheduler
+                .skip_idle(TickDevice::Uhci, uhci.controller().is_idle())
+            {
+                let skipped = tick_scheduler::skip_ms_ticks(&mut self.uhci_ns_remainder, delta_ns);
+                let counter = &mut self.perf.skipped_device_ticks.uhci;
+                *counter = counter.wrapping_add(skipped);
+            } else {
+                self.uhci_ns_remainder = self.uhci_ns_remainder.saturating_add(delta_ns);
+                let mut ticks = self.uhci_ns_remainder / NS_PER_MS;
+                self.uhci_ns_remainder %= NS_PER_MS;
+
+                while ticks != 0 {
+                    uhci.tick_1ms(&mut self.mem.bus);
+                    perf::bump(&mut self.perf.device_ticks.uhci);
+                    ticks -= 1;
+                }
             }
         }
 
@@ -8381,16 +11517,27 @@ impl Machine {
                     .set_bar_base(EhciPciDevice::MMIO_BAR_INDEX, bar0_base);
             }
 
-            self.ehci_ns_remainder = self.ehci_ns_remainder.saturating_add(delta_ns);
-            let mut ticks = self.ehci_ns_remainder / NS_PER_MS;
-            self.ehci_ns_remainder %= NS_PER_MS;
-
-            while ticks != 0 {
-                ehci.tick_1ms(&mut self.mem.bus);
-                ticks -= 1;
+            if self
+                .tick_scheduler
+                .skip_idle(TickDevice::Ehci, ehci.controller().is_idle())
+            {
+                let skipped = tick_scheduler::skip_ms_ticks(&mut self.ehci_ns_remainder, delta_ns);
+                let counter = &mut self.perf.skipped_device_ticks.ehci;
+                *counter = counter.wrapping_add(skipped);
+            } else {
+                self.ehci_ns_remainder = self.ehci_ns_remainder.saturating_add(delta_ns);
+                let mut ticks = self.ehci_ns_remainder / NS_PER_MS;
+                self.ehci_ns_remainder %= NS_PER_MS;
+
+                while ticks != 0 {
+                    ehci.tick_1ms(&mut self.mem.bus);
+                    perf::bump(&mut self.perf.device_ticks.ehci);
+                    ticks -= 1;
+                }
             }
         }
 
+        // xHCI is not scheduled: its MFINDEX and port timers advance even while halted.
         if let Some(xhci) = self.xhci.as_ref() {
             const NS_PER_MS: u64 = 1_000_000;
 
@@ -8441,6 +11588,7 @@ impl Machine {
 
             while ticks != 0 {
                 xhci.tick_1ms(&mut self.mem.bus);
+                perf::bump(&mut self.perf.device_ticks.xhci);
                 ticks -= 1;
             }
 
@@ -8533,7 +11681,11 @@ impl Machine {
         }
 
         // Advance 1ms worth of CPU cycles while halted so timer devices can wake the CPU.
-        let cycles = (tsc_hz / 1000).max(1);
+        self.advance_idle_cycles((tsc_hz / 1000).max(1));
+    }
+
+    /// Advance the TSC and platform time by `cycles` without executing instructions.
+    fn advance_idle_cycles(&mut self, cycles: u64) {
         self.cpu.time.advance_cycles(cycles);
         self.cpu.state.msr.tsc = self.cpu.time.read_tsc();
         self.tick_platform_from_cycles(cycles);
@@ -8547,6 +11699,12 @@ impl Machine {
         self.guest_time.resync_from_tsc(self.cpu.state.msr.tsc);
     }
 
+    fn pci_intx_router_config(&self) -> PciIntxRouterConfig {
+        PciIntxRouterConfig {
+            pirq_to_gsi: self.cfg.pci_pirq_to_gsi,
+        }
+    }
+
     fn sync_pci_intx_sources_to_interrupts(&mut self) {
         let Some(interrupts) = &self.interrupts else {
             return;
@@ -8556,6 +11714,28 @@ impl Machine {
             let mut pci_intx = pci_intx.borrow_mut();
             let mut interrupts = interrupts.borrow_mut();
 
+            // Apply the guest-programmed PIIX3 PIRQ route registers before sampling device levels
+            // so asserted INTx lines follow a PIRQ that was just moved or disabled.
+            if let Some(pci_cfg) = &self.pci_cfg {
+                let mut pci_cfg = pci_cfg.borrow_mut();
+                if let Some(cfg) = pci_cfg
+                    .bus_mut()
+                    .device_config_mut(aero_devices::pci::profile::ISA_PIIX3.bdf)
+                {
+                    for pirq in 0..4 {
+                        // PIRQs hardwired to I/O APIC inputs beyond the ISA range are not
+                        // controlled by PIRQRC.
+                        if self.cfg.pci_pirq_to_gsi[pirq] >= 16 {
+                            continue;
+                        }
+                        let value =
+                            cfg.read(aero_devices::pci::PIIX3_PIRQ_ROUTE_OFFSET + pirq as u16, 1);
+                        let gsi = aero_devices::pci::piix3_pirq_route_to_gsi(value as u8);
+                        pci_intx.set_pirq_route(pirq, gsi, &mut *interrupts);
+                    }
+                }
+            }
+
             // E1000 legacy INTx (level-triggered).
             if let Some(e1000) = &self.e1000 {
                 let bdf: PciBdf = aero_devices::pci::profile::NIC_E1000_82540EM.bdf;
@@ -8867,9 +12047,9 @@ impl Machine {
                 pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
             }
 
-            // virtio-blk legacy INTx (level-triggered).
-            if let Some(virtio_blk) = &self.virtio_blk {
-                let bdf: PciBdf = aero_devices::pci::profile::VIRTIO_BLK.bdf;
+            // virtio-balloon legacy INTx (level-triggered).
+            if let Some(virtio_balloon) = &self.virtio_balloon {
+                let bdf: PciBdf = aero_devices::pci::profile::VIRTIO_BALLOON.bdf;
                 let pin = PciInterruptPin::IntA;
 
                 let (command, msix_enabled, msix_masked) = self
@@ -8891,17 +12071,12 @@ impl Machine {
                     })
                     .unwrap_or((0, false, false));
 
-                // Keep the virtio transport's internal PCI command register in sync so it can
-                // respect COMMAND.INTX_DISABLE gating.
-                {
-                    let mut dev = virtio_blk.borrow_mut();
+                let mut level = {
+                    let mut dev = virtio_balloon.borrow_mut();
                     sync_msix_capability_into_config(dev.config_mut(), msix_enabled, msix_masked);
                     dev.set_pci_command(command);
-                }
-
-                let mut level = virtio_blk.borrow().irq_level();
-
-                // Redundantly gate on the canonical PCI command register as well (defensive).
+                    dev.irq_level()
+                };
                 if (command & (1 << 10)) != 0 {
                     level = false;
                 }
@@ -8909,23 +12084,36 @@ impl Machine {
                 pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
             }
 
-            // AeroGPU legacy INTx (level-triggered).
-            if let Some(aerogpu) = &self.aerogpu_mmio {
-                let bdf: PciBdf = aero_devices::pci::profile::AEROGPU.bdf;
+            // virtio-9p legacy INTx (level-triggered).
+            if let Some(virtio_9p) = &self.virtio_9p {
+                let bdf: PciBdf = aero_devices::pci::profile::VIRTIO_9P.bdf;
                 let pin = PciInterruptPin::IntA;
 
-                let (command, mut level) = {
-                    let mut dev = aerogpu.borrow_mut();
-                    let command = self
-                        .pci_cfg
-                        .as_ref()
-                        .map(|pci_cfg| sync_aerogpu_pci_state_into_mmio(pci_cfg, &mut dev))
-                        .unwrap_or(0);
-                    let level = dev.irq_level();
-                    (command, level)
-                };
+                let (command, msix_enabled, msix_masked) = self
+                    .pci_cfg
+                    .as_ref()
+                    .map(|pci_cfg| {
+                        let mut pci_cfg = pci_cfg.borrow_mut();
+                        match pci_cfg.bus_mut().device_config(bdf) {
+                            Some(cfg) => {
+                                let msix = cfg.capability::<MsixCapability>();
+                                (
+                                    cfg.command(),
+                                    msix.is_some_and(|msix| msix.enabled()),
+                                    msix.is_some_and(|msix| msix.function_masked()),
+                                )
+                            }
+                            None => (0, false, false),
+                        }
+                    })
+                    .unwrap_or((0, false, false));
 
-                // Redundantly gate on the canonical PCI command register as well (defensive).
+                let mut level = {
+                    let mut dev = virtio_9p.borrow_mut();
+                    sync_msix_capability_into_config(dev.config_mut(), msix_enabled, msix_masked);
+                    dev.set_pci_command(command);
+                    dev.irq_level()
+                };
                 if (command & (1 << 10)) != 0 {
                     level = false;
                 }
@@ -8933,27 +12121,130 @@ impl Machine {
                 pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
             }
 
-            // PIIX3 UHCI legacy INTx (level-triggered).
-            if let Some(uhci) = &self.uhci {
-                let bdf: PciBdf = aero_devices::pci::profile::USB_UHCI_PIIX3.bdf;
+            // virtio-rng legacy INTx (level-triggered).
+            if let Some(virtio_rng) = &self.virtio_rng {
+                let bdf: PciBdf = aero_devices::pci::profile::VIRTIO_RNG.bdf;
                 let pin = PciInterruptPin::IntA;
 
-                // Keep the device model's internal PCI command state coherent so
-                // `UhciPciDevice::irq_level()` and `UhciPciDevice::tick_1ms` can apply
-                // COMMAND.INTX_DISABLE and COMMAND.BME gating.
-                let (command, bar4_base) = self
+                let (command, msix_enabled, msix_masked) = self
                     .pci_cfg
                     .as_ref()
                     .map(|pci_cfg| {
                         let mut pci_cfg = pci_cfg.borrow_mut();
-                        let cfg = pci_cfg.bus_mut().device_config(bdf);
-                        let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
-                        let bar4_base = cfg
-                            .and_then(|cfg| cfg.bar_range(UhciPciDevice::IO_BAR_INDEX))
-                            .map(|range| range.base);
-                        (command, bar4_base)
+                        match pci_cfg.bus_mut().device_config(bdf) {
+                            Some(cfg) => {
+                                let msix = cfg.capability::<MsixCapability>();
+                                (
+                                    cfg.command(),
+                                    msix.is_some_and(|msix| msix.enabled()),
+                                    msix.is_some_and(|msix| msix.function_masked()),
+                                )
+                            }
+                            None => (0, false, false),
+                        }
                     })
-                    .unwrap_or((0, None));
+                    .unwrap_or((0, false, false));
+
+                let mut level = {
+                    let mut dev = virtio_rng.borrow_mut();
+                    sync_msix_capability_into_config(dev.config_mut(), msix_enabled, msix_masked);
+                    dev.set_pci_command(command);
+                    dev.irq_level()
+                };
+                if (command & (1 << 10)) != 0 {
+                    level = false;
+                }
+
+                pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
+            }
+
+            // virtio-blk legacy INTx (level-triggered).
+            if let Some(virtio_blk) = &self.virtio_blk {
+                let bdf: PciBdf = aero_devices::pci::profile::VIRTIO_BLK.bdf;
+                let pin = PciInterruptPin::IntA;
+
+                let (command, msix_enabled, msix_masked) = self
+                    .pci_cfg
+                    .as_ref()
+                    .map(|pci_cfg| {
+                        let mut pci_cfg = pci_cfg.borrow_mut();
+                        match pci_cfg.bus_mut().device_config(bdf) {
+                            Some(cfg) => {
+                                let msix = cfg.capability::<MsixCapability>();
+                                (
+                                    cfg.command(),
+                                    msix.is_some_and(|msix| msix.enabled()),
+                                    msix.is_some_and(|msix| msix.function_masked()),
+                                )
+                            }
+                            None => (0, false, false),
+                        }
+                    })
+                    .unwrap_or((0, false, false));
+
+                // Keep the virtio transport's internal PCI command register in sync so it can
+                // respect COMMAND.INTX_DISABLE gating.
+                {
+                    let mut dev = virtio_blk.borrow_mut();
+                    sync_msix_capability_into_config(dev.config_mut(), msix_enabled, msix_masked);
+                    dev.set_pci_command(command);
+                }
+
+                let mut level = virtio_blk.borrow().irq_level();
+
+                // Redundantly gate on the canonical PCI command register as well (defensive).
+                if (command & (1 << 10)) != 0 {
+                    level = false;
+                }
+
+                pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
+            }
+
+            // AeroGPU legacy INTx (level-triggered).
+            if let Some(aerogpu) = &self.aerogpu_mmio {
+                let bdf: PciBdf = aero_devices::pci::profile::AEROGPU.bdf;
+                let pin = PciInterruptPin::IntA;
+
+                let (command, mut level) = {
+                    let mut dev = aerogpu.borrow_mut();
+                    let command = self
+                        .pci_cfg
+                        .as_ref()
+                        .map(|pci_cfg| sync_aerogpu_pci_state_into_mmio(pci_cfg, &mut dev))
+                        .unwrap_or(0);
+                    let level = dev.irq_level();
+                    (command, level)
+                };
+
+                // Redundantly gate on the canonical PCI command register as well (defensive).
+                if (command & (1 << 10)) != 0 {
+                    level = false;
+                }
+
+                pci_intx.set_intx_level(bdf, pin, level, &mut *interrupts);
+            }
+
+            // PIIX3 UHCI legacy INTx (level-triggered).
+            if let Some(uhci) = &self.uhci {
+                let bdf: PciBdf = aero_devices::pci::profile::USB_UHCI_PIIX3.bdf;
+                let pin = PciInterruptPin::IntA;
+
+                // Keep the device model's internal PCI command state coherent so
+                // `UhciPciDevice::irq_level()` and `UhciPciDevice::tick_1ms` can apply
+                // COMMAND.INTX_DISABLE and COMMAND.BME gating.
+                let (command, bar4_base) = self
+                    .pci_cfg
+                    .as_ref()
+                    .map(|pci_cfg| {
+                        let mut pci_cfg = pci_cfg.borrow_mut();
+                        let cfg = pci_cfg.bus_mut().device_config(bdf);
+                        let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
+                        let bar4_base = cfg
+                            .and_then(|cfg| cfg.bar_range(UhciPciDevice::IO_BAR_INDEX))
+                            .map(|range| range.base);
+                        (command, bar4_base)
+                    })
+                    .unwrap_or((0, None));
 
                 let mut uhci_dev = uhci.borrow_mut();
                 uhci_dev.config_mut().set_command(command);
@@ -9101,24 +12392,195 @@ impl Machine {
             }
         }
 
-        // COM1 serial (16550) uses ISA IRQ4 on PC-compatible platforms. The UART's interrupt output
-        // is level-based (asserted while an interrupt condition is pending); the platform interrupt
-        // router converts this into an edge for the legacy PIC, and into a level for IOAPIC mode.
-        if let Some(serial) = &self.serial {
-            let level = serial.borrow().irq_level();
-            let mut interrupts = interrupts.borrow_mut();
-            if level {
-                interrupts.raise_irq(InterruptInput::IsaIrq(4));
-            } else {
-                interrupts.lower_irq(InterruptInput::IsaIrq(4));
-            }
+        // COM ports (16550) use ISA IRQ4 (COM1/COM3) and IRQ3 (COM2/COM4) by default. The UART's
+        // interrupt output is level-based (asserted while an interrupt condition is pending); the
+        // platform interrupt router converts this into an edge for the legacy PIC, and into a level
+        // for IOAPIC mode. Ports sharing an IRQ are wired-OR onto a single line.
+        for (irq, line) in &self.serial_irq_lines {
+            let level = self.serial.iter().enumerate().any(|(port, uart)| {
+                self.cfg.serial_irqs[port] == *irq
+                    && uart.as_ref().is_some_and(|uart| uart.borrow().irq_level())
+            });
+            line.set_level(level);
         }
     }
 
-    /// Take (drain) all serial output accumulated so far.
+    /// Take (drain) all COM1 serial output accumulated so far.
     pub fn take_serial_output(&mut self) -> Vec<u8> {
+        self.take_serial_port_output(0)
+    }
+
+    /// Take (drain) all output accumulated so far on COM port `port` (`0` = COM1).
+    ///
+    /// Returns an empty buffer for ports that are out of range or not enabled.
+    pub fn take_serial_port_output(&mut self, port: usize) -> Vec<u8> {
+        self.flush_serial();
+        self.serial_logs
+            .get_mut(port)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Return the number of bytes buffered in the output log of COM port `port` (`0` = COM1).
+    pub fn serial_port_output_len(&mut self, port: usize) -> u64 {
+        self.flush_serial();
+        self.serial_logs
+            .get(port)
+            .map_or(0, |log| u64::try_from(log.len()).unwrap_or(u64::MAX))
+    }
+
+    /// Bound the output log of every COM port to the most recent `capacity` bytes (`None` removes
+    /// the bound, which is the default).
+    ///
+    /// Older bytes are dropped as new output arrives, so a host that consumes output through
+    /// [`Machine::add_serial_sink`] can keep a short tail for [`Machine::serial_output_bytes`]
+    /// without the log growing without limit.
+    pub fn set_serial_log_capacity(&mut self, capacity: Option<usize>) {
+        self.flush_serial();
+        self.serial_log_capacity = capacity;
+        for log in &mut self.serial_logs {
+            serial_ports::truncate_log(log, capacity);
+        }
+    }
+
+    /// The bound set by [`Machine::set_serial_log_capacity`].
+    pub fn serial_log_capacity(&self) -> Option<usize> {
+        self.serial_log_capacity
+    }
+
+    /// Add a sink that receives every byte the guest transmits on COM port `port` (`0` = COM1).
+    ///
+    /// Sinks see output in addition to its normal destination (output log, [`SerialChannel`] or
+    /// KD bridge), in the order they were added, starting with bytes transmitted after this call.
+    /// They are called when the machine drains UART output: as [`Machine::run_slice`] returns and
+    /// before any serial getter, never from inside guest I/O dispatch. A sink must not call back
+    /// into the machine; it runs while the machine is mutably borrowed, so a host holding the
+    /// machine in a `RefCell` would panic rather than re-enter it. Sinks are host-side wiring: they
+    /// survive [`Machine::reset`] and snapshot restore and are not snapshotted.
+    ///
+    /// Returns `None` (dropping the sink) when the port is out of range or not enabled.
+    pub fn add_serial_sink(&mut self, port: usize, sink: SerialSink) -> Option<SerialSinkId> {
+        if !Self::serial_port_enabled(&self.cfg, port) {
+            return None;
+        }
+        self.flush_serial();
+        Some(self.serial_sinks.add(port, sink))
+    }
+
+    /// Remove (and drop) a sink added with [`Machine::add_serial_sink`], after delivering the
+    /// output transmitted so far. Returns `false` if the sink was already removed.
+    pub fn remove_serial_sink(&mut self, id: SerialSinkId) -> bool {
+        self.flush_serial();
+        self.serial_sinks.remove(id)
+    }
+
+    /// Queue host input bytes in the receive FIFO of COM port `port` (`0` = COM1).
+    ///
+    /// Returns `false` (dropping the bytes) when the port is out of range or not enabled.
+    pub fn serial_write_input(&mut self, port: usize, bytes: &[u8]) -> bool {
+        let Some(Some(uart)) = self.serial.get(port) else {
+            return false;
+        };
+        let mut uart = uart.borrow_mut();
+        for &byte in bytes {
+            uart.push_rx(byte);
+        }
+        true
+    }
+
+    /// Return a bidirectional byte-channel handle for COM port `port` (`0` = COM1).
+    ///
+    /// While a channel is attached, guest transmit bytes are routed to it instead of the port's
+    /// output log, and bytes sent through it are fed to the UART's receive FIFO. This lets hosts
+    /// bridge a port to an external transport (e.g. a WebSocket carrying a kernel debugger
+    /// session). Repeated calls return handles to the same channel.
+    ///
+    /// Returns `None` when the port is out of range or not enabled.
+    pub fn serial_channel(&mut self, port: usize) -> Option<SerialChannel> {
+        if !Self::serial_port_enabled(&self.cfg, port) {
+            return None;
+        }
+        self.flush_serial();
+        Some(
+            self.serial_channels[port]
+                .get_or_insert_with(Default::default)
+                .clone(),
+        )
+    }
+
+    /// Detach the byte channel from COM port `port`, resuming output logging.
+    pub fn detach_serial_channel(&mut self, port: usize) {
+        self.flush_serial();
+        if let Some(channel) = self.serial_channels.get_mut(port) {
+            *channel = None;
+        }
+    }
+
+    /// Route COM port `port` (`0` = COM1) through the kernel debugger bridge.
+    ///
+    /// Guest output on the port is queued as timestamped [`KdChunk`]s (or, with
+    /// [`Machine::kd_set_packet_framing`], as delimited [`KdFrame`]s) instead of the port's output
+    /// log or [`SerialChannel`]. Only one port can be bridged; enabling the bridge again moves it
+    /// and discards queued output. The bridge is host-side state: it survives reset and snapshot
+    /// restore, and is not snapshotted.
+    ///
+    /// Returns `false` when the port is out of range or not enabled.
+    pub fn enable_kd_bridge(&mut self, port: usize) -> bool {
+        if !Self::serial_port_enabled(&self.cfg, port) {
+            return false;
+        }
+        self.flush_serial();
+        self.kd_bridge = Some(kd_bridge::KdBridge::new(port));
+        true
+    }
+
+    /// Disable the kernel debugger bridge, discarding queued output.
+    pub fn disable_kd_bridge(&mut self) {
         self.flush_serial();
-        std::mem::take(&mut self.serial_log)
+        self.kd_bridge = None;
+    }
+
+    /// COM port index routed through the kernel debugger bridge, if enabled.
+    pub fn kd_bridge_port(&self) -> Option<usize> {
+        self.kd_bridge.as_ref().map(|bridge| bridge.port)
+    }
+
+    /// Enable or disable KD packet framing for the bridge.
+    ///
+    /// While enabled, guest output is delimited into packets retrieved with
+    /// [`Machine::kd_next_frame`] and [`Machine::kd_take_from_guest`] returns nothing. Toggling
+    /// framing discards bytes that were not yet retrieved through the previous mode.
+    pub fn kd_set_packet_framing(&mut self, enabled: bool) {
+        self.flush_serial();
+        if let Some(bridge) = &mut self.kd_bridge {
+            bridge.from_guest.clear();
+            bridge.framer = enabled.then(KdPacketFramer::new);
+        }
+    }
+
+    /// Drain the guest output queued on the bridged port.
+    pub fn kd_take_from_guest(&mut self) -> Vec<KdChunk> {
+        self.flush_serial();
+        self.kd_bridge
+            .as_mut()
+            .map(|bridge| bridge.from_guest.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    /// Pop the oldest delimited frame of guest output (packet framing only).
+    pub fn kd_next_frame(&mut self) -> Option<KdFrame> {
+        self.flush_serial();
+        self.kd_bridge.as_mut()?.framer.as_mut()?.next_frame()
+    }
+
+    /// Queue debugger bytes in the bridged port's receive FIFO.
+    ///
+    /// Returns `false` (dropping the bytes) when the bridge is not enabled.
+    pub fn kd_send_to_guest(&mut self, bytes: &[u8]) -> bool {
+        match self.kd_bridge_port() {
+            Some(port) => self.serial_write_input(port, bytes),
+            None => false,
+        }
     }
 
     /// Return a copy of the serial output accumulated so far without draining it.
@@ -9127,7 +12589,7 @@ impl Machine {
     /// [`Machine::serial_output_len`].
     pub fn serial_output_bytes(&mut self) -> Vec<u8> {
         self.flush_serial();
-        self.serial_log.clone()
+        self.serial_logs[0].clone()
     }
 
     /// Return the number of bytes currently buffered in the serial output log.
@@ -9136,7 +12598,7 @@ impl Machine {
     /// byte count (e.g. UI progress indicators) and want to avoid copying large buffers.
     pub fn serial_output_len(&mut self) -> u64 {
         self.flush_serial();
-        u64::try_from(self.serial_log.len()).unwrap_or(u64::MAX)
+        u64::try_from(self.serial_logs[0].len()).unwrap_or(u64::MAX)
     }
 
     /// Returns the BIOS "TTY output" buffer accumulated so far.
@@ -9182,6 +12644,72 @@ impl Machine {
         u64::try_from(self.debugcon_log.borrow().len()).unwrap_or(u64::MAX)
     }
 
+    /// Choose which [`MachineEventCategory`] values the unified event stream records (see
+    /// [`Machine::take_events`]). Nothing is recorded by default.
+    ///
+    /// The mask, capacity and undrained events are host state: they are not snapshotted and
+    /// survive [`Machine::reset`].
+    pub fn set_event_mask(&mut self, mask: MachineEventMask) {
+        self.events.set_mask(mask);
+        // Unclaimed accesses only reach the tracker while its bus hooks are installed.
+        self.install_unknown_access_hooks();
+    }
+
+    /// The categories currently recorded by the event stream.
+    pub fn event_mask(&self) -> MachineEventMask {
+        self.events.mask()
+    }
+
+    /// Bound the event stream to `capacity` undrained events (at least one; default
+    /// [`MACHINE_EVENT_DEFAULT_CAPACITY`]).
+    ///
+    /// A full stream drops new events instead of evicting undrained ones; drops are counted per
+    /// category (see [`Machine::take_event_drops`]). Shrinking below the number of undrained events
+    /// discards the newest of them, which also counts as dropped.
+    pub fn set_event_capacity(&mut self, capacity: usize) {
+        self.events.set_capacity(capacity);
+    }
+
+    /// The bound set by [`Machine::set_event_capacity`].
+    pub fn event_capacity(&self) -> usize {
+        self.events.capacity()
+    }
+
+    /// Drain up to `max` events from the unified event stream, oldest first.
+    ///
+    /// Events from every enabled category share one sequence: order by [`MachineEvent::seq`] for
+    /// the global timeline. [`MachineEvent::guest_time_ns`] is non-decreasing between resets;
+    /// events recorded within one CPU batch share the batch's start time.
+    pub fn take_events(&mut self, max: usize) -> Vec<MachineEvent> {
+        self.events.take(max)
+    }
+
+    /// Number of undrained events in the stream.
+    pub fn pending_event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Events dropped because the stream was full since the previous call, per category.
+    pub fn take_event_drops(&mut self) -> MachineEventDrops {
+        self.events.take_drops()
+    }
+
+    /// Most recent POST code written to port `0x80`, by the BIOS or the guest, since the last
+    /// reset.
+    pub fn last_post_code(&self) -> Option<u8> {
+        self.post_codes.last()
+    }
+
+    /// POST codes written to port `0x80` since the last reset, oldest first, with the guest time
+    /// of each write.
+    ///
+    /// BIOS codes decode with [`BiosPostPhase::from_code`]; guest and option ROM codes are
+    /// recorded as written. At most [`POST_CODE_HISTORY_CAPACITY`] codes are kept, discarding the
+    /// oldest.
+    pub fn post_code_history(&self) -> Vec<PostCodeRecord> {
+        self.post_codes.history()
+    }
+
     /// Whether the legacy PS/2 i8042 controller is present.
     ///
     /// This is a lightweight machine-wiring check intended for host/runtime input backend
@@ -9349,6 +12877,46 @@ impl Machine {
         self.inject_key_scancode_bytes(bytes);
     }
 
+    fn poll_boot_menu(&mut self) -> bool {
+        let mut cdrom = self.install_media.as_ref().and_then(InstallMedia::upgrade);
+        let cdrom = cdrom
+            .as_mut()
+            .map(|iso| iso as &mut dyn firmware::bios::CdromDevice);
+        let mut floppy = self.floppy.clone();
+        let floppy = floppy
+            .as_mut()
+            .map(|floppy| floppy as &mut dyn firmware::bios::BlockDevice);
+        // Only lend the network backend to the poll that is about to try a network boot.
+        let network_boot = self
+            .bios
+            .pending_boot_menu_order(1, cdrom.is_some())
+            .is_some_and(|order| order.contains(&firmware::bios::BootEntry::Network));
+        let mut nic = if network_boot {
+            self.take_pxe_nic()
+        } else {
+            None
+        };
+        let nic_ref = nic
+            .as_mut()
+            .map(|nic| nic as &mut dyn firmware::bios::PxeNic);
+        let bus: &mut dyn BiosBus = &mut self.mem;
+        let waiting = self.bios.poll_boot_menu(
+            &mut self.cpu.state,
+            bus,
+            &mut self.disk,
+            floppy,
+            cdrom,
+            nic_ref,
+            1,
+        );
+        self.return_pxe_nic(nic);
+        self.drain_bios_post_codes();
+        if !waiting && self.cpu.state.halted {
+            self.mirror_bios_panic_to_serial();
+        }
+        waiting
+    }
+
     /// Inject a classic BIOS keyboard word into the firmware INT 16h queue.
     ///
     /// `key` uses the historical BIOS encoding: `(scan_code << 8) | ascii`.
@@ -9370,16 +12938,26 @@ impl Machine {
     ///
     /// `dx` is positive to the right and `dy` is positive down (browser-style). The underlying PS/2
     /// mouse model converts this into PS/2 packet coordinates (+Y is up).
+    ///
+    /// Motion is subject to [`MachineConfig::pointer_coalescing`].
     pub fn inject_mouse_motion(&mut self, dx: i32, dy: i32, wheel: i32) {
-        if let Some(ctrl) = &self.i8042 {
-            ctrl.borrow_mut().inject_mouse_motion(dx, dy, wheel);
+        if self.i8042.is_none() {
+            return;
         }
+        let now_ns = self.guest_now_ns();
+        self.ps2_mouse_backlog
+            .push_motion(self.cfg.pointer_coalescing, now_ns, dx, dy, wheel, 0);
+        self.flush_ps2_mouse_backlog(false);
     }
 
     /// Inject a PS/2 mouse button transition into the i8042 controller, if present.
+    ///
+    /// Any coalesced motion injected before the transition is delivered ahead of it.
     pub fn inject_mouse_button(&mut self, button: Ps2MouseButton, pressed: bool) {
-        if let Some(ctrl) = &self.i8042 {
-            ctrl.borrow_mut().inject_mouse_button(button, pressed);
+        if self.i8042.is_some() {
+            let now_ns = self.guest_now_ns();
+            self.ps2_mouse_backlog.push_button(now_ns, button, pressed);
+            self.flush_ps2_mouse_backlog(false);
         }
 
         // Keep the absolute-mask helper (`inject_ps2_mouse_buttons`) coherent even if callers mix
@@ -9448,6 +13026,20 @@ impl Machine {
         self.inject_mouse_motion(dx, 0i32.saturating_sub(dy), wheel);
     }
 
+    /// Inject horizontal wheel movement (positive = right) into the PS/2 mouse, if present.
+    ///
+    /// Only reported once the guest enabled IntelliMouse Explorer 4.0 horizontal scrolling; like
+    /// motion, it is subject to [`MachineConfig::pointer_coalescing`].
+    pub fn inject_ps2_mouse_hwheel(&mut self, delta: i32) {
+        if self.i8042.is_none() || delta == 0 {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        self.ps2_mouse_backlog
+            .push_motion(self.cfg.pointer_coalescing, now_ns, 0, 0, 0, delta);
+        self.flush_ps2_mouse_backlog(false);
+    }
+
     /// Inject a PS/2 mouse button state into the i8042 controller, if present.
     ///
     /// `buttons` is a bitmask:
@@ -9474,8 +13066,13 @@ impl Machine {
         // `ps2_mouse_buttons` is a host-side cache used to compute transitions from an absolute
         // button mask. Prefer the authoritative guest device state when the i8042 controller is
         // present: the guest can reset/reconfigure the mouse independently, making the cached value
-        // stale.
-        let prev = if let Some(ctrl) = &self.i8042 {
+        // stale. Transitions still waiting in the host backlog (device queue full) are not visible
+        // there yet, so fall back to the cache while any are pending.
+        let prev = if let Some(ctrl) = self
+            .i8042
+            .as_ref()
+            .filter(|_| !self.ps2_mouse_backlog.has_pending_buttons())
+        {
             ctrl.borrow().mouse_buttons_mask() & 0x1f
         } else {
             self.ps2_mouse_buttons & 0x1f
@@ -9574,56 +13171,28 @@ impl Machine {
     /// Inject a Linux input relative motion event (`EV_REL` + `REL_X/REL_Y`) into the virtio-input
     /// mouse device.
     ///
+    /// Motion and wheel injections are subject to [`MachineConfig::pointer_coalescing`].
+    ///
     /// This is a no-op when virtio-input is disabled.
     pub fn inject_virtio_rel(&mut self, dx: i32, dy: i32) {
-        let Some(mouse) = &self.virtio_input_mouse else {
-            return;
-        };
-        {
-            let mut dev = mouse.borrow_mut();
-            let Some(input) = dev.device_mut::<VirtioInput>() else {
-                return;
-            };
-            input.inject_rel_move(dx, dy);
-        }
-        self.process_virtio_input();
-        self.sync_pci_intx_sources_to_interrupts();
+        self.queue_virtio_mouse_motion(dx, dy, 0, 0);
+        self.deliver_virtio_mouse_input();
     }
 
     /// Inject a Linux input button event (`EV_KEY` + `BTN_*`) into the virtio-input mouse device.
     ///
     /// This is a no-op when virtio-input is disabled.
     pub fn inject_virtio_button(&mut self, btn: u16, pressed: bool) {
-        let Some(mouse) = &self.virtio_input_mouse else {
-            return;
-        };
-        {
-            let mut dev = mouse.borrow_mut();
-            let Some(input) = dev.device_mut::<VirtioInput>() else {
-                return;
-            };
-            input.inject_button(btn, pressed);
-        }
-        self.process_virtio_input();
-        self.sync_pci_intx_sources_to_interrupts();
+        self.queue_virtio_mouse_button(btn, pressed);
+        self.deliver_virtio_mouse_input();
     }
 
     /// Inject a Linux mouse wheel event (`EV_REL` + `REL_WHEEL`) into the virtio-input mouse device.
     ///
     /// This is a no-op when virtio-input is disabled.
     pub fn inject_virtio_wheel(&mut self, delta: i32) {
-        let Some(mouse) = &self.virtio_input_mouse else {
-            return;
-        };
-        {
-            let mut dev = mouse.borrow_mut();
-            let Some(input) = dev.device_mut::<VirtioInput>() else {
-                return;
-            };
-            input.inject_wheel(delta);
-        }
-        self.process_virtio_input();
-        self.sync_pci_intx_sources_to_interrupts();
+        self.queue_virtio_mouse_motion(0, 0, delta, 0);
+        self.deliver_virtio_mouse_input();
     }
 
     // Explicit aliases for parity with the wasm-facing API.
@@ -9646,18 +13215,8 @@ impl Machine {
     ///
     /// This is a no-op when virtio-input is disabled.
     pub fn inject_virtio_hwheel(&mut self, delta: i32) {
-        let Some(mouse) = &self.virtio_input_mouse else {
-            return;
-        };
-        {
-            let mut dev = mouse.borrow_mut();
-            let Some(input) = dev.device_mut::<VirtioInput>() else {
-                return;
-            };
-            input.inject_hwheel(delta);
-        }
-        self.process_virtio_input();
-        self.sync_pci_intx_sources_to_interrupts();
+        self.queue_virtio_mouse_motion(0, 0, 0, delta);
+        self.deliver_virtio_mouse_input();
     }
 
     /// Inject a Linux mouse vertical + horizontal wheel update into the virtio-input mouse device.
@@ -9667,18 +13226,8 @@ impl Machine {
     ///
     /// This is a no-op when virtio-input is disabled.
     pub fn inject_virtio_wheel2(&mut self, wheel: i32, hwheel: i32) {
-        let Some(mouse) = &self.virtio_input_mouse else {
-            return;
-        };
-        {
-            let mut dev = mouse.borrow_mut();
-            let Some(input) = dev.device_mut::<VirtioInput>() else {
-                return;
-            };
-            input.inject_wheel2(wheel, hwheel);
-        }
-        self.process_virtio_input();
-        self.sync_pci_intx_sources_to_interrupts();
+        self.queue_virtio_mouse_motion(0, 0, wheel, hwheel);
+        self.deliver_virtio_mouse_input();
     }
 
     /// Inject a Linux input absolute motion event (`EV_ABS` + `ABS_X/ABS_Y`) into the virtio-input
@@ -9705,6 +13254,127 @@ impl Machine {
         self.inject_virtio_abs(x, y);
     }
 
+    fn queue_virtio_mouse_motion(&mut self, dx: i32, dy: i32, wheel: i32, hwheel: i32) {
+        if self.virtio_input_mouse.is_none() {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        self.virtio_mouse_backlog.push_motion(
+            self.cfg.pointer_coalescing,
+            now_ns,
+            dx,
+            dy,
+            wheel,
+            hwheel,
+        );
+    }
+
+    fn queue_virtio_mouse_button(&mut self, btn: u16, pressed: bool) {
+        // Linux input defines code 0 as BTN_RESERVED; the device ignores it.
+        if self.virtio_input_mouse.is_none() || btn == 0 {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        self.virtio_mouse_backlog.push_button(now_ns, btn, pressed);
+    }
+
+    fn deliver_virtio_mouse_input(&mut self) {
+        if self.flush_virtio_mouse_backlog(false) {
+            self.process_virtio_input();
+            // Ensure legacy INTx routing reflects the device's updated IRQ latch immediately,
+            // without requiring a subsequent `run_slice` call.
+            self.sync_pci_intx_sources_to_interrupts();
+        }
+    }
+
+    /// Hand pending virtio mouse frames to the device if they are due (or `force` is set).
+    /// Returns whether the device's event queue changed.
+    fn flush_virtio_mouse_backlog(&mut self, force: bool) -> bool {
+        if self.virtio_mouse_backlog.is_empty() {
+            return false;
+        }
+        let now_ns = self.guest_now_ns();
+        if !force
+            && !self
+                .virtio_mouse_backlog
+                .is_due(self.cfg.pointer_coalescing, now_ns)
+        {
+            return false;
+        }
+        let Some(mouse) = &self.virtio_input_mouse else {
+            self.virtio_mouse_backlog.clear();
+            return false;
+        };
+        let mut dev = mouse.borrow_mut();
+        let Some(input) = dev.device_mut::<VirtioInput>() else {
+            self.virtio_mouse_backlog.clear();
+            return false;
+        };
+        self.virtio_mouse_backlog.flush(
+            self.cfg.pointer_queue_full_policy,
+            now_ns,
+            &mut pointer_coalesce::VirtioMouseSink(input),
+        )
+    }
+
+    /// Hand pending PS/2 mouse frames to the i8042 if they are due (or `force` is set).
+    fn flush_ps2_mouse_backlog(&mut self, force: bool) {
+        if self.ps2_mouse_backlog.is_empty() {
+            return;
+        }
+        let now_ns = self.guest_now_ns();
+        if !force
+            && !self
+                .ps2_mouse_backlog
+                .is_due(self.cfg.pointer_coalescing, now_ns)
+        {
+            return;
+        }
+        let Some(ctrl) = &self.i8042 else {
+            self.ps2_mouse_backlog.clear();
+            return;
+        };
+        let mut ctrl = ctrl.borrow_mut();
+        self.ps2_mouse_backlog.flush(
+            self.cfg.pointer_queue_full_policy,
+            now_ns,
+            &mut pointer_coalesce::Ps2MouseSink(&mut ctrl),
+        );
+    }
+
+    fn flush_pointer_backlogs(&mut self, force: bool) {
+        let before = self
+            .input_latency
+            .is_some()
+            .then(|| self.input_latency_counters(false));
+        let virtio_dirty = self.flush_virtio_mouse_backlog(force);
+        self.flush_ps2_mouse_backlog(force);
+        self.record_input_latency_pointer_flush(before);
+        if virtio_dirty {
+            self.process_virtio_input();
+            self.sync_pci_intx_sources_to_interrupts();
+        }
+    }
+
+    /// Deliver all coalesced pointer input (virtio-input and PS/2 mouse) to the guest devices now,
+    /// without waiting for the next `run_slice` or the end of the coalescing window.
+    ///
+    /// Frames the device has no room for are still subject to
+    /// [`MachineConfig::pointer_queue_full_policy`].
+    pub fn flush_pointer_input(&mut self) {
+        self.flush_pointer_backlogs(true);
+    }
+
+    /// Injection telemetry for the virtio-input mouse path.
+    pub fn virtio_mouse_input_counters(&self) -> PointerEventCounters {
+        self.virtio_mouse_backlog.counters()
+    }
+
+    /// Injection telemetry for the PS/2 mouse path.
+    pub fn ps2_mouse_input_counters(&self) -> PointerEventCounters {
+        self.ps2_mouse_backlog.counters()
+    }
+
     // ---------------------------------------------------------------------
     // Synthetic USB HID devices (UHCI external hub)
     // ---------------------------------------------------------------------
@@ -9715,6 +13385,7 @@ impl Machine {
     pub fn inject_usb_hid_keyboard_usage(&mut self, usage: u8, pressed: bool) {
         if let Some(kbd) = &self.usb_hid_keyboard {
             kbd.key_event(usage, pressed);
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9725,6 +13396,7 @@ impl Machine {
     pub fn inject_usb_hid_mouse_move(&mut self, dx: i32, dy: i32) {
         if let Some(mouse) = &self.usb_hid_mouse {
             mouse.movement(dx, dy);
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9748,6 +13420,7 @@ impl Machine {
             if buttons != 0 {
                 mouse.button_event(buttons, true);
             }
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9758,6 +13431,7 @@ impl Machine {
     pub fn inject_usb_hid_mouse_wheel(&mut self, delta: i32) {
         if let Some(mouse) = &self.usb_hid_mouse {
             mouse.wheel(delta);
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9768,6 +13442,7 @@ impl Machine {
     pub fn inject_usb_hid_mouse_hwheel(&mut self, delta: i32) {
         if let Some(mouse) = &self.usb_hid_mouse {
             mouse.hwheel(delta);
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9783,6 +13458,7 @@ impl Machine {
     pub fn inject_usb_hid_mouse_wheel2(&mut self, wheel: i32, hwheel: i32) {
         if let Some(mouse) = &self.usb_hid_mouse {
             mouse.wheel2(wheel, hwheel);
+            self.usb_input_deliveries += 1;
         }
     }
 
@@ -9814,6 +13490,7 @@ impl Machine {
             rx,
             ry,
         });
+        self.usb_input_deliveries += 1;
     }
 
     /// Inject a USB HID Consumer Control usage event into the machine's synthetic consumer-control
@@ -9833,12 +13510,77 @@ impl Machine {
             return;
         }
         consumer.consumer_event(usage, pressed);
+        self.usb_input_deliveries += 1;
     }
 
     // ---------------------------------------------------------------------
     // Input batching (InputEventQueue wire format)
     // ---------------------------------------------------------------------
 
+    /// Select the scanout head that host pointer input belongs to.
+    ///
+    /// `MouseAbsolute` input batch events without an explicit head are interpreted in the focused
+    /// head's coordinate space. Relative pointer and keyboard events are not affected.
+    ///
+    /// This is host policy: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_input_focus(&mut self, head: u8) {
+        self.input_focus_head = head;
+    }
+
+    /// Return the scanout head selected by [`Machine::set_input_focus`].
+    pub fn input_focus(&self) -> u8 {
+        self.input_focus_head
+    }
+
+    /// Set whether the guest has grabbed the host keyboard.
+    ///
+    /// While grabbed (the default), every keyboard event in an input batch is delivered. While not
+    /// grabbed, key presses of the HID usages configured via
+    /// [`Machine::set_keyboard_grab_filter`] are dropped so host/browser shortcuts are not swallowed
+    /// by the guest. Key releases are always delivered so no key can get stuck across a toggle.
+    ///
+    /// This is host policy: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_keyboard_grab(&mut self, grabbed: bool) {
+        self.keyboard_grab = grabbed;
+    }
+
+    /// Return the keyboard grab state set by [`Machine::set_keyboard_grab`].
+    pub fn keyboard_grab(&self) -> bool {
+        self.keyboard_grab
+    }
+
+    /// Replace the set of keyboard HID usages (Usage Page 0x07) filtered while the keyboard is not
+    /// grabbed. Defaults to the Left/Right GUI keys (`0xE3`, `0xE7`).
+    pub fn set_keyboard_grab_filter(&mut self, usages: &[u8]) {
+        self.keyboard_grab_filter = usages.to_vec();
+    }
+
+    /// Return the keyboard HID usages filtered while the keyboard is not grabbed.
+    pub fn keyboard_grab_filter(&self) -> &[u8] {
+        &self.keyboard_grab_filter
+    }
+
+    /// Current resolution of scanout `head`, or `None` if the head is not scanning out.
+    ///
+    /// Head 0 follows the active scanout source: AeroGPU scanout 0 once WDDM owns it, otherwise the
+    /// last presented legacy VGA/VBE frame. No other heads exist yet.
+    fn input_head_resolution(&self, head: u8) -> Option<(u32, u32)> {
+        if head != 0 {
+            return None;
+        }
+        let (width, height) = match &self.aerogpu_mmio {
+            Some(aerogpu_mmio) if self.active_scanout_source() == ScanoutSource::Wddm => {
+                let state = aerogpu_mmio.borrow().scanout0_state();
+                if !state.enable {
+                    return None;
+                }
+                (state.width, state.height)
+            }
+            _ => self.display_resolution(),
+        };
+        (width != 0 && height != 0).then_some((width, height))
+    }
+
     /// Inject a batch of input events encoded in the `InputEventQueue` wire format used by the
     /// web runtime.
     ///
@@ -9849,8 +13591,22 @@ impl Machine {
     /// - Header (2 words): `[count, batch_timestamp_us]`
     /// - Events: `count` entries, each 4 words: `[ty, event_timestamp_us, a, b]`
     ///
+    /// `event_timestamp_us` is the host timestamp of the event in wrapping microseconds, or 0 if
+    /// the host has none. It is only reported back in input latency samples (see
+    /// [`Machine::start_input_latency_sampling`]); the batch timestamp is ignored.
+    ///
     /// Event type values are defined by `web/src/input/event_queue.ts::InputEventType`.
     ///
+    /// ## Host input policy
+    ///
+    /// - `MouseAbsolute` positions are given in pixels of a scanout head (the focused head unless
+    ///   the event names one, see [`Machine::set_input_focus`]) and are scaled into the virtio-input
+    ///   tablet's `ABS_X`/`ABS_Y` range. Events for heads that are not scanning out are dropped.
+    ///   Relative pointer events are delivered unchanged regardless of focus.
+    /// - While the keyboard is not grabbed ([`Machine::set_keyboard_grab`]), key presses of the
+    ///   filtered HID usages are dropped, both as `KeyHidUsage` events and as the matching PS/2
+    ///   set-2 make sequences in `KeyScancode` events.
+    ///
     /// ## Defensive parsing
     ///
     /// - Malformed/truncated buffers are ignored without panicking.
@@ -9865,6 +13621,11 @@ impl Machine {
         const TYPE_GAMEPAD_REPORT: u32 = 5;
         const TYPE_KEY_HID_USAGE: u32 = 6;
         const TYPE_HID_USAGE16: u32 = 7;
+        const TYPE_MOUSE_ABSOLUTE: u32 = 8;
+
+        // `MouseAbsolute` head field: bit 8 set = explicit head in bits 0..7, otherwise the focused
+        // head.
+        const MOUSE_ABSOLUTE_HEAD_PRESENT: u32 = 1 << 8;
 
         const HEADER_WORDS: usize = 2;
         const WORDS_PER_EVENT: usize = 4;
@@ -10213,14 +13974,35 @@ impl Machine {
             })
         }
 
+        // PS/2 set-2 make sequences of the HID usages filtered while the keyboard is not grabbed.
+        let filtered_scancode_makes: Vec<Vec<u8>> = if self.keyboard_grab {
+            Vec::new()
+        } else {
+            self.keyboard_grab_filter
+                .iter()
+                .filter_map(|&usage| hid_usage_to_browser_code(usage))
+                .filter_map(|code| {
+                    aero_devices_input::scancode::browser_code_to_set2_bytes(&code, true)
+                })
+                .collect()
+        };
+
+        // Input latency sampling compares device counters around each event. Events bail out with
+        // `continue`, so an event's deliveries are recorded when the next one starts (and after the
+        // loop).
+        let mut latency_event = None;
         for i in 0..count {
+            self.record_input_latency_event(latency_event.take());
             let off = HEADER_WORDS + i * WORDS_PER_EVENT;
             let ty = words[off];
-            // `event_timestamp_us` is currently unused by the machine; keep parsing in case future
-            // runtimes want to drive guest-time heuristics from it.
-            let _event_timestamp_us = words[off + 1];
+            // `event_timestamp_us` is only used as the host timestamp of input latency samples.
+            let event_timestamp_us = words[off + 1];
             let a = words[off + 2];
             let b = words[off + 3];
+            if self.input_latency.is_some() {
+                let host_ts = (event_timestamp_us != 0).then_some(event_timestamp_us);
+                latency_event = Some((host_ts, self.input_latency_counters(false)));
+            }
 
             match ty {
                 TYPE_KEY_SCANCODE => {
@@ -10238,6 +14020,12 @@ impl Machine {
                     for (j, slot) in bytes.iter_mut().enumerate().take(len) {
                         *slot = ((a >> (j * 8)) & 0xff) as u8;
                     }
+                    if filtered_scancode_makes
+                        .iter()
+                        .any(|make| make.as_slice() == &bytes[..len])
+                    {
+                        continue;
+                    }
                     self.inject_key_scancode_bytes(&bytes[..len]);
                 }
                 TYPE_KEY_HID_USAGE => {
@@ -10261,6 +14049,13 @@ impl Machine {
                         continue;
                     }
 
+                    // Not grabbed: drop filtered key presses before they are tracked so the
+                    // backend selection is unaffected. Releases always pass.
+                    if pressed && !self.keyboard_grab && self.keyboard_grab_filter.contains(&usage)
+                    {
+                        continue;
+                    }
+
                     // Track pressed keyboard usages regardless of the current backend selection so
                     // backend switching can be gated on "any key is held".
                     let idx = usage as usize;
@@ -10448,15 +14243,7 @@ impl Machine {
                         self.inject_ps2_mouse_motion(dx, dy_ps2, 0);
                     } else if use_virtio_mouse {
                         // virtio-input uses Linux REL_Y where positive = down.
-                        let Some(mouse) = &self.virtio_input_mouse else {
-                            continue;
-                        };
-                        let mut dev = mouse.borrow_mut();
-                        let Some(input) = dev.device_mut::<VirtioInput>() else {
-                            continue;
-                        };
-                        input.inject_rel_move(dx, dy_down);
-                        virtio_input_dirty = true;
+                        self.queue_virtio_mouse_motion(dx, dy_down, 0, 0);
                     } else if use_usb_mouse {
                         // USB HID follows DOM/browser convention where +Y is down.
                         self.inject_usb_hid_mouse_move(dx, dy_down);
@@ -10488,20 +14275,18 @@ impl Machine {
                             self.inject_usb_hid_mouse_buttons(0);
                         }
                         if virtio_mouse_driver_ok {
-                            if let Some(mouse) = &self.virtio_input_mouse {
-                                let mut dev = mouse.borrow_mut();
-                                if let Some(input) = dev.device_mut::<VirtioInput>() {
-                                    use aero_virtio::devices::input::*;
-                                    input.inject_button(BTN_LEFT, false);
-                                    input.inject_button(BTN_RIGHT, false);
-                                    input.inject_button(BTN_MIDDLE, false);
-                                    input.inject_button(BTN_SIDE, false);
-                                    input.inject_button(BTN_EXTRA, false);
-                                    input.inject_button(BTN_FORWARD, false);
-                                    input.inject_button(BTN_BACK, false);
-                                    input.inject_button(BTN_TASK, false);
-                                    virtio_input_dirty = true;
-                                }
+                            use aero_virtio::devices::input::*;
+                            for btn in [
+                                BTN_LEFT,
+                                BTN_RIGHT,
+                                BTN_MIDDLE,
+                                BTN_SIDE,
+                                BTN_EXTRA,
+                                BTN_FORWARD,
+                                BTN_BACK,
+                                BTN_TASK,
+                            ] {
+                                self.queue_virtio_mouse_button(btn, false);
                             }
                         }
                         // Clear any invalid marker used by other mouse injection helpers.
@@ -10525,67 +14310,31 @@ impl Machine {
                             continue;
                         }
 
-                        let Some(mouse) = &self.virtio_input_mouse else {
-                            continue;
-                        };
-                        let mut dev = mouse.borrow_mut();
-                        let Some(input) = dev.device_mut::<VirtioInput>() else {
+                        if self.virtio_input_mouse.is_none() {
                             continue;
-                        };
-                        // Match DOM `MouseEvent.buttons` bit mapping.
-                        if (changed & 0x01) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_LEFT,
-                                next & 0x01 != 0,
-                            );
-                        }
-                        if (changed & 0x02) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_RIGHT,
-                                next & 0x02 != 0,
-                            );
-                        }
-                        if (changed & 0x04) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_MIDDLE,
-                                next & 0x04 != 0,
-                            );
-                        }
-                        if (changed & 0x08) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_SIDE,
-                                next & 0x08 != 0,
-                            );
                         }
-                        if (changed & 0x10) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_EXTRA,
-                                next & 0x10 != 0,
-                            );
-                        }
-                        // Additional mouse buttons (6..8). These are not typically surfaced via
-                        // DOM `MouseEvent.buttons`, but are supported by the input batch wire
-                        // format and advertised by Aero's virtio-input mouse device model.
-                        if (changed & 0x20) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_FORWARD,
-                                next & 0x20 != 0,
-                            );
-                        }
-                        if (changed & 0x40) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_BACK,
-                                next & 0x40 != 0,
-                            );
-                        }
-                        if (changed & 0x80) != 0 {
-                            input.inject_button(
-                                aero_virtio::devices::input::BTN_TASK,
-                                next & 0x80 != 0,
-                            );
+                        // Match DOM `MouseEvent.buttons` bit mapping (bits 0..4). Bits 5..7 are not
+                        // typically surfaced via DOM `MouseEvent.buttons`, but are supported by the
+                        // input batch wire format and advertised by Aero's virtio-input mouse
+                        // device model.
+                        use aero_virtio::devices::input::*;
+                        let buttons = [
+                            BTN_LEFT,
+                            BTN_RIGHT,
+                            BTN_MIDDLE,
+                            BTN_SIDE,
+                            BTN_EXTRA,
+                            BTN_FORWARD,
+                            BTN_BACK,
+                            BTN_TASK,
+                        ];
+                        for (bit, btn) in buttons.into_iter().enumerate() {
+                            let mask = 1u8 << bit;
+                            if (changed & mask) != 0 {
+                                self.queue_virtio_mouse_button(btn, next & mask != 0);
+                            }
                         }
                         self.ps2_mouse_buttons = next;
-                        virtio_input_dirty = true;
                     } else if use_usb_mouse {
                         self.inject_usb_hid_mouse_buttons(next & 0x1f);
                         self.ps2_mouse_buttons = next;
@@ -10597,18 +14346,13 @@ impl Machine {
                     if use_ps2_mouse {
                         // Payload:
                         //   a = dz (signed 32-bit), positive = wheel up
-                        let _ = dx;
-                        self.inject_ps2_mouse_motion(0, 0, dz);
+                        //   b = dx (signed 32-bit), positive = right
+                        if dz != 0 {
+                            self.inject_ps2_mouse_motion(0, 0, dz);
+                        }
+                        self.inject_ps2_mouse_hwheel(dx);
                     } else if use_virtio_mouse {
-                        let Some(mouse) = &self.virtio_input_mouse else {
-                            continue;
-                        };
-                        let mut dev = mouse.borrow_mut();
-                        let Some(input) = dev.device_mut::<VirtioInput>() else {
-                            continue;
-                        };
-                        input.inject_wheel2(dz, dx);
-                        virtio_input_dirty = true;
+                        self.queue_virtio_mouse_motion(0, 0, dz, dx);
                     } else if use_usb_mouse {
                         self.inject_usb_hid_mouse_wheel2(dz, dx);
                     }
@@ -10616,11 +14360,46 @@ impl Machine {
                 TYPE_GAMEPAD_REPORT => {
                     self.inject_usb_hid_gamepad_report(a, b);
                 }
+                TYPE_MOUSE_ABSOLUTE => {
+                    // Payload:
+                    //   a = (x & 0xFFFF) | ((y & 0xFFFF) << 16), in pixels of the target head
+                    //   b = (head & 0xFF) | (1 << 8) to name a head; 0 = focused head
+                    let head = if (b & MOUSE_ABSOLUTE_HEAD_PRESENT) != 0 {
+                        (b & 0xff) as u8
+                    } else {
+                        self.input_focus_head
+                    };
+                    let Some((width, height)) = self.input_head_resolution(head) else {
+                        continue;
+                    };
+                    let Some(tablet) = &self.virtio_input_tablet else {
+                        continue;
+                    };
+                    // Map pixel 0 and the last pixel onto the ends of the tablet range.
+                    fn scale(pos: u32, extent: u32) -> i32 {
+                        use aero_virtio::devices::input::{TABLET_ABS_MAX, TABLET_ABS_MIN};
+                        let last = extent - 1;
+                        if last == 0 {
+                            return TABLET_ABS_MIN;
+                        }
+                        let span = (TABLET_ABS_MAX - TABLET_ABS_MIN) as u64;
+                        TABLET_ABS_MIN + (u64::from(pos.min(last)) * span / u64::from(last)) as i32
+                    }
+                    let x = scale(a & 0xffff, width);
+                    let y = scale(a >> 16, height);
+                    let mut dev = tablet.borrow_mut();
+                    let Some(input) = dev.device_mut::<VirtioInput>() else {
+                        continue;
+                    };
+                    input.inject_abs_move(x, y);
+                    virtio_input_dirty = true;
+                }
                 _ => {
                     // Unknown event type; ignore.
                 }
             }
         }
+        self.record_input_latency_event(latency_event);
 
         // Re-evaluate keyboard backend selection after processing the batch: key-up events can make
         // it safe to switch away from PS/2 or USB injection.
@@ -10648,6 +14427,13 @@ impl Machine {
             };
         }
 
+        // Hand over mouse frames queued by this batch (subject to pointer coalescing).
+        let before = self
+            .input_latency
+            .is_some()
+            .then(|| self.input_latency_counters(false));
+        virtio_input_dirty |= self.flush_virtio_mouse_backlog(false);
+        self.record_input_latency_pointer_flush(before);
         if virtio_input_dirty {
             // Poll once to forward any newly enqueued input events into guest virtqueues.
             self.process_virtio_input();
@@ -10676,6 +14462,44 @@ impl Machine {
         self.save_snapshot_to(w, options)
     }
 
+    /// Run up to `max_insts` instructions in chunks of `probe_every_n_insts`, snapshotting at every
+    /// chunk boundary.
+    ///
+    /// This is a test-support API for save/restore determinism checks: at each boundary the COM1
+    /// output of the chunk is drained, a full snapshot is taken and a [`StateFingerprint`] of the
+    /// machine is recorded. Restoring any probe's snapshot into a fresh machine and running it with
+    /// the same chunk size must reproduce the fingerprints of all later probes. The run ends early
+    /// (after probing) on any exit other than [`RunExit::Completed`].
+    pub fn run_slice_with_snapshot_probe(
+        &mut self,
+        max_insts: u64,
+        probe_every_n_insts: u64,
+    ) -> snapshot::Result<SnapshotProbeRun> {
+        let chunk = probe_every_n_insts.max(1);
+        let mut executed = 0u64;
+        let mut probes = Vec::new();
+        loop {
+            let exit = self.run_slice(chunk.min(max_insts - executed));
+            executed += exit.executed();
+
+            let serial = self.take_serial_output();
+            let snapshot = self.take_snapshot_full()?;
+            let fingerprint = StateFingerprint::capture(&*self, serial)?;
+            probes.push(SnapshotProbe {
+                executed,
+                snapshot,
+                fingerprint,
+            });
+
+            if !matches!(exit, RunExit::Completed { .. }) || executed >= max_insts {
+                return Ok(SnapshotProbeRun {
+                    exit: exit.with_executed(executed),
+                    probes,
+                });
+            }
+        }
+    }
+
     pub fn restore_snapshot_bytes(&mut self, bytes: &[u8]) -> snapshot::Result<()> {
         self.restore_snapshot_from_checked(&mut Cursor::new(bytes))
     }
@@ -10689,28 +14513,85 @@ impl Machine {
     pub fn restore_snapshot_from_checked<R: Read + Seek>(
         &mut self,
         r: &mut R,
+    ) -> snapshot::Result<()> {
+        self.restore_snapshot_checked_impl(r, None)
+    }
+
+    /// Save a full snapshot, passing each compressed RAM block through `transform` (e.g. to
+    /// encrypt guest memory at rest).
+    ///
+    /// `metadata` is stored unencrypted next to the RAM header (e.g. a key id, salt or nonce) and
+    /// is reported by [`snapshot::inspect_snapshot`]. Blocks are transformed one at a time, so the
+    /// transform never sees more than one RAM chunk.
+    pub fn save_snapshot_with_transform<W: Write + Seek>(
+        &mut self,
+        w: &mut W,
+        metadata: &[u8],
+        transform: snapshot::RamBlockTransform<'_>,
+    ) -> snapshot::Result<()> {
+        self.flush_serial();
+        snapshot::save_snapshot_with_ram_transform(
+            w,
+            self,
+            snapshot::SaveOptions::default(),
+            metadata,
+            transform,
+        )
+    }
+
+    /// Restore a snapshot written by [`Machine::save_snapshot_with_transform`]; `transform` must
+    /// invert the save-side transform.
+    pub fn restore_snapshot_with_transform<R: Read + Seek>(
+        &mut self,
+        r: &mut R,
+        transform: snapshot::RamBlockTransform<'_>,
+    ) -> snapshot::Result<()> {
+        self.restore_snapshot_checked_impl(r, Some(transform))
+    }
+
+    fn restore_snapshot_checked_impl<R: Read + Seek>(
+        &mut self,
+        r: &mut R,
+        transform: Option<snapshot::RamBlockTransform<'_>>,
     ) -> snapshot::Result<()> {
         // Restoring a snapshot is conceptually "rewinding time", so discard any accumulated host
         // output/state from the current execution.
         self.detach_network();
         self.flush_serial();
-        if let Some(uart) = &self.serial {
+        for uart in self.serial.iter().flatten() {
             let _ = uart.borrow_mut().take_tx();
         }
-        self.serial_log.clear();
+        for log in &mut self.serial_logs {
+            log.clear();
+        }
         self.debugcon_log.borrow_mut().clear();
-        self.reset_latch.clear();
+        self.clear_reset_request();
+        // A snapshot captures a running guest.
+        self.acpi_sleep_latch.set(None);
+        self.power_state = GuestPowerState::Running;
         // Clear restore-only state before applying new snapshot sections.
         self.restored_disk_overlays = None;
 
-        let expected_parent_snapshot_id = self.last_snapshot_id;
-        snapshot::restore_snapshot_with_options(
-            r,
-            self,
-            snapshot::RestoreOptions {
-                expected_parent_snapshot_id,
-            },
-        )
+        let opts = snapshot::RestoreOptions {
+            expected_parent_snapshot_id: self.last_snapshot_id,
+        };
+        match transform {
+            Some(transform) => {
+                snapshot::restore_snapshot_with_ram_transform(r, self, opts, transform)
+            }
+            None => snapshot::restore_snapshot_with_options(r, self, opts),
+        }
+    }
+
+    /// Save a snapshot with explicit `options` (RAM mode, compression, zero-page elision),
+    /// returning the raw and encoded RAM sizes so hosts can report the savings.
+    pub fn save_snapshot_with_options_to<W: Write + Seek>(
+        &mut self,
+        w: &mut W,
+        options: snapshot::SaveOptions,
+    ) -> snapshot::Result<snapshot::RamSectionStats> {
+        self.flush_serial();
+        snapshot::save_snapshot_with_stats(w, self, options)
     }
 
     fn save_snapshot_to<W: Write + Seek>(
@@ -10718,8 +14599,7 @@ impl Machine {
         w: &mut W,
         options: snapshot::SaveOptions,
     ) -> snapshot::Result<()> {
-        self.flush_serial();
-        snapshot::save_snapshot(w, self, options)
+        self.save_snapshot_with_options_to(w, options).map(|_| ())
     }
 
     fn take_snapshot_with_options(
@@ -10731,47 +14611,331 @@ impl Machine {
         Ok(cursor.into_inner())
     }
 
-    /// Reset the machine and transfer control to firmware POST (boot sector).
-    pub fn reset(&mut self) {
-        self.reset_latch.clear();
-        self.serial_log.clear();
-        self.debugcon_log.borrow_mut().clear();
-        self.ps2_mouse_buttons = 0;
-        self.consumer_usage_backend.fill(0);
-        self.input_batch_pressed_keyboard_usages.fill(0);
-        self.input_batch_pressed_keyboard_usage_count = 0;
-        self.input_batch_keyboard_backend = 0;
-        self.input_batch_mouse_buttons_mask = 0;
-        self.input_batch_mouse_backend = 0;
-        self.guest_time.reset();
-        self.uhci_ns_remainder = 0;
-        self.ehci_ns_remainder = 0;
-        self.xhci_ns_remainder = 0;
-        self.restored_disk_overlays = None;
-        self.display_fb.clear();
-        self.display_width = 0;
-        self.display_height = 0;
-        self.ide_irq14_line = None;
-        self.ide_irq15_line = None;
+    /// List the device entries a snapshot of this machine would contain, with each entry's id,
+    /// version and current state size.
+    ///
+    /// Ids that hold several entries (e.g. [`snapshot::DeviceId::DISK_CONTROLLER`]) are listed once
+    /// per entry. Hosts can use this to describe a snapshot ("contains devices X, Y, Z").
+    pub fn device_snapshot_manifest(&self) -> Vec<DeviceSnapshotInfo> {
+        snapshot::SnapshotSource::device_states(self)
+            .iter()
+            .map(DeviceSnapshotInfo::from_state)
+            .collect()
+    }
 
-        // Reset chipset lines.
-        self.chipset.a20().set_enabled(false);
+    /// Serialize the current state of the device entries stored under `id`, framed as in the
+    /// snapshot `DEVICES` section. Returns `None` if this machine has no such device.
+    ///
+    /// Together with [`Machine::load_device_state`] this supports targeted round-trips and
+    /// checked-in compatibility fixtures without taking full snapshots.
+    pub fn save_device_state(&self, id: snapshot::DeviceId) -> Option<Vec<u8>> {
+        let states = snapshot::SnapshotSource::device_states(self);
+        let matching: Vec<&snapshot::DeviceState> = states.iter().filter(|s| s.id == id).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        device_snapshot::encode_states(&matching).ok()
+    }
 
-        // Rebuild port I/O devices for deterministic power-on state.
-        self.io = IoPortBus::new();
-        // Expose the Bochs/QEMU-style debug console port (0xE9) for low-overhead early-boot output.
-        if self.cfg.enable_debugcon {
-            register_debugcon(&mut self.io, self.debugcon_log.clone());
+    /// Load device state produced by [`Machine::save_device_state`] (possibly by an older build)
+    /// into the device entries stored under `id`.
+    ///
+    /// This behaves like restoring a snapshot in which only these entries differ from the current
+    /// machine state: all other devices are re-applied from their current state and the usual
+    /// post-restore cleanup runs (e.g. the network backend is detached). `aero-io-snapshot`
+    /// payloads are checked for the device's 4CC, a supported major version and a well-formed TLV
+    /// stream before anything is applied, since restore itself applies them best-effort.
+    pub fn load_device_state(
+        &mut self,
+        id: snapshot::DeviceId,
+        bytes: &[u8],
+    ) -> snapshot::Result<()> {
+        let incoming = device_snapshot::decode_states(id, bytes)?;
+        let mut states = snapshot::SnapshotSource::device_states(self);
+
+        for state in &incoming {
+            // Multi-entry ids (disk controllers) are told apart by their io-snapshot 4CC.
+            let inner = device_snapshot::io_device_id(&state.data);
+            let idx = states
+                .iter()
+                .position(|s| s.id == id && device_snapshot::io_device_id(&s.data) == inner)
+                .or_else(|| states.iter().position(|s| s.id == id))
+                .ok_or(snapshot::SnapshotError::Corrupt("device not present"))?;
+            device_snapshot::validate_io_payload(state, &states[idx])?;
+            states[idx] = state.clone();
         }
 
-        let use_legacy_vga = self.cfg.enable_vga && !self.cfg.enable_aerogpu;
+        snapshot::SnapshotTarget::restore_device_states(self, states);
+        snapshot::SnapshotTarget::post_restore(self)
+    }
 
-        // `enable_vga` controls whether the machine wires the legacy VGA/VBE device model.
-        //
-        // Note: The physical memory bus persists across `Machine::reset()` calls, so any MMIO
-        // mappings that reference the VGA device must keep a stable `Rc` identity across resets.
-        // When VGA is disabled we drop the device handle, but any previously-installed MMIO
-        // mappings (if the machine was ever reset with VGA enabled) will still point at the old
+    /// Source of the guest reset request the host has not acted on yet.
+    ///
+    /// A reset reported by [`RunExit::ResetRequested`] stays pending until [`Machine::reset`] /
+    /// [`Machine::reset_with_kind`], a snapshot restore or [`Machine::cancel_pending_reset`]; until
+    /// then every `run_slice` reports it again without executing. This lets the host inspect or
+    /// snapshot the pre-reset state before deciding how to handle the request. Requests a device
+    /// latched since the last `run_slice` (e.g. host port I/O to `0xCF9`) are included.
+    pub fn pending_reset(&self) -> Option<ResetSource> {
+        if let Some((_, source)) = &self.pending_reset {
+            return Some(source.clone());
+        }
+        self.reset_latch.peek()?;
+        Some(
+            self.reset_source
+                .borrow()
+                .clone()
+                .unwrap_or(ResetSource::Unknown),
+        )
+    }
+
+    /// Drop the pending guest reset request so the next `run_slice` resumes the guest where it
+    /// stopped (after a triple fault, with whatever CPU state the failed delivery left behind).
+    pub fn cancel_pending_reset(&mut self) {
+        self.clear_reset_request();
+    }
+
+    fn clear_reset_request(&mut self) {
+        self.reset_latch.clear();
+        self.reset_source.borrow_mut().take();
+        self.pending_reset = None;
+    }
+
+    fn reset_sink(&self, source: ResetSource) -> SourcedResetSink {
+        SourcedResetSink::new(self.reset_latch.clone(), self.reset_source.clone(), source)
+    }
+
+    fn record_reset_event(&self, kind: ResetKind, source: &ResetSource) {
+        if self
+            .events
+            .wants(machine_events::MachineEventCategory::ResetRequested)
+        {
+            self.events.record_at(
+                self.guest_now_ns(),
+                machine_events::MachineEventKind::ResetRequested {
+                    kind,
+                    source: source.clone(),
+                },
+            );
+        }
+    }
+
+    /// Report the pending reset request, first moving a newly latched one into `pending_reset`.
+    fn poll_reset_request(&mut self, executed: u64) -> Option<RunExit> {
+        if self.pending_reset.is_none() {
+            let kind = self.reset_latch.take()?;
+            let source = self
+                .reset_source
+                .borrow_mut()
+                .take()
+                .unwrap_or(ResetSource::Unknown);
+            self.record_reset_event(kind, &source);
+            self.pending_reset = Some((kind, source));
+        }
+        let (kind, source) = self.pending_reset.clone()?;
+        Some(RunExit::ResetRequested {
+            kind,
+            source,
+            executed,
+        })
+    }
+
+    /// Reset the machine and transfer control to firmware POST (boot sector).
+    ///
+    /// This is a cold ([`ResetKind::System`]) reset; see [`Machine::reset_with_kind`].
+    pub fn reset(&mut self) {
+        self.reset_with_kind(ResetKind::System);
+    }
+
+    /// Reset the machine as requested by [`RunExit::ResetRequested`].
+    ///
+    /// Neither kind zeroes guest RAM. [`ResetKind::System`] (cold) additionally resets the RTC/CMOS
+    /// to its power-on contents and clears AeroGPU VRAM. [`ResetKind::Cpu`] (warm) keeps RTC time
+    /// and CMOS contents as well as AeroGPU VRAM; devices are otherwise reset as for a cold reset.
+    ///
+    /// On a warm reset the firmware honours the CMOS shutdown status byte (register `0x0F`, cleared
+    /// by the reset): codes `0x05`, `0x0A`, `0x0B` and `0x0C` skip POST and resume in real mode
+    /// through the BDA pointer at 40:67 (see [`firmware::bios::Bios::resume_from_shutdown`]); any
+    /// other value runs POST.
+    pub fn reset_with_kind(&mut self, kind: ResetKind) {
+        self.reset_with_post(true, kind);
+    }
+
+    /// Reset the machine, skip firmware POST, and enter a flat payload directly.
+    ///
+    /// Devices are left in their post-reset state (in particular, PCI devices are not enumerated
+    /// or assigned BARs, and no BIOS tables or interrupt vectors are installed). The machine writes
+    /// the flat GDT (protected/long mode) and any generated identity-map page tables, loads
+    /// `spec.blobs`, enables the A20 gate and starts the BSP at `spec.entry`; application
+    /// processors stay in their wait-for-SIPI state.
+    ///
+    /// The entry point must lie in guest RAM (unchecked for [`DirectBootPaging::Provided`] page
+    /// tables, where it is a virtual address) and long mode requires paging. On error the machine
+    /// is left untouched.
+    pub fn boot_direct(&mut self, spec: DirectBootSpec) -> Result<(), DirectBootError> {
+        let identity_map = match (spec.mode, spec.paging) {
+            (DirectBootMode::Real, DirectBootPaging::Disabled) => None,
+            (DirectBootMode::Real, _) => return Err(DirectBootError::RealModePagingUnsupported),
+            (DirectBootMode::Long64, DirectBootPaging::Disabled) => {
+                return Err(DirectBootError::LongModeRequiresPaging)
+            }
+            (mode, DirectBootPaging::IdentityMap { gib, tables_paddr }) => {
+                let tables = direct_boot::identity_map_tables(mode, gib, tables_paddr)?;
+                if !self.guest_ram_contains(tables_paddr, tables.len) {
+                    return Err(DirectBootError::PageTablesOutsideRam(tables_paddr));
+                }
+                Some(tables)
+            }
+            _ => None,
+        };
+
+        let entry_limit = match spec.mode {
+            DirectBootMode::Real => 0x10_0000,
+            DirectBootMode::Protected32 => 1 << 32,
+            DirectBootMode::Long64 => u64::MAX,
+        };
+        let entry_checked = !matches!(spec.paging, DirectBootPaging::Provided { .. });
+        if spec.entry >= entry_limit || (entry_checked && !self.guest_ram_contains(spec.entry, 1)) {
+            return Err(DirectBootError::EntryOutsideRam(spec.entry));
+        }
+        let gdt_len = (direct_boot::DIRECT_BOOT_GDT.len() * 8) as u64;
+        if spec.mode != DirectBootMode::Real && !self.guest_ram_contains(spec.gdt_paddr, gdt_len) {
+            return Err(DirectBootError::GdtOutsideRam(spec.gdt_paddr));
+        }
+        for (paddr, bytes) in &spec.blobs {
+            let len = bytes.len() as u64;
+            if !self.guest_ram_contains(*paddr, len) {
+                return Err(DirectBootError::BlobOutsideRam { paddr: *paddr, len });
+            }
+        }
+
+        self.reset_with_post(false, ResetKind::System);
+        // Open the A20 gate before loading anything so writes above 1MiB are not aliased.
+        self.chipset.a20().set_enabled(true);
+
+        if spec.mode != DirectBootMode::Real {
+            for (i, desc) in direct_boot::DIRECT_BOOT_GDT.iter().enumerate() {
+                self.mem.write_u64(spec.gdt_paddr + (i as u64) * 8, *desc);
+            }
+        }
+        let paging = match (spec.paging, identity_map) {
+            (_, Some(tables)) => {
+                self.mem
+                    .write_physical(tables.cr3, &vec![0u8; tables.len as usize]);
+                for (paddr, entry) in tables.entries {
+                    self.mem.write_u64(paddr, entry);
+                }
+                Some(vcpu_init::DirectBootPagingState {
+                    cr3: tables.cr3,
+                    pae: true,
+                })
+            }
+            (DirectBootPaging::Provided { cr3 }, None) => Some(vcpu_init::DirectBootPagingState {
+                cr3,
+                pae: spec.mode == DirectBootMode::Long64,
+            }),
+            _ => None,
+        };
+        for (paddr, bytes) in &spec.blobs {
+            self.mem.write_physical(*paddr, bytes);
+        }
+
+        vcpu_init::init_bsp_vcpu_for_direct_boot(
+            &mut self.cpu,
+            spec.mode,
+            spec.entry,
+            spec.gpr,
+            spec.rflags,
+            spec.gdt_paddr,
+            paging,
+        );
+        self.mem.clear_dirty();
+        Ok(())
+    }
+
+    /// Whether `[paddr, paddr + len)` is backed by guest RAM (accounting for RAM remapped above
+    /// 4GiB around the PCI hole).
+    fn guest_ram_contains(&self, paddr: u64, len: u64) -> bool {
+        let Some(end) = paddr.checked_add(len) else {
+            return false;
+        };
+        let ram = self.cfg.ram_size_bytes;
+        let low_ram_end = firmware::bios::PCIE_ECAM_BASE;
+        if end <= low_ram_end.min(ram) {
+            return true;
+        }
+        let high_ram_len = ram.saturating_sub(low_ram_end);
+        paddr >= FOUR_GIB && end <= FOUR_GIB + high_ram_len
+    }
+
+    fn reset_with_post(&mut self, run_post: bool, kind: ResetKind) {
+        let warm = kind == ResetKind::Cpu;
+        // Capture RTC/CMOS state before the platform clock is rewound; restoring it against the
+        // rewound clock keeps the RTC's wall time running across the reset.
+        let warm_rtc_state = self
+            .rtc
+            .as_ref()
+            .filter(|_| warm)
+            .map(|rtc| rtc.borrow().save_state());
+        let mut shutdown_status = 0u8;
+        self.clear_reset_request();
+        self.acpi_sleep_latch.set(None);
+        self.power_state = GuestPowerState::Running;
+        for log in &mut self.serial_logs {
+            log.clear();
+        }
+        self.debugcon_log.borrow_mut().clear();
+        self.post_codes.clear();
+        self.ps2_mouse_buttons = 0;
+        self.virtio_mouse_backlog.clear();
+        self.ps2_mouse_backlog.clear();
+        self.consumer_usage_backend.fill(0);
+        self.input_batch_pressed_keyboard_usages.fill(0);
+        self.input_batch_pressed_keyboard_usage_count = 0;
+        self.input_batch_keyboard_backend = 0;
+        self.input_batch_mouse_buttons_mask = 0;
+        self.input_batch_mouse_backend = 0;
+        self.guest_time.reset();
+        self.uhci_ns_remainder = 0;
+        self.ehci_ns_remainder = 0;
+        self.xhci_ns_remainder = 0;
+        self.tick_scheduler.wake_all();
+        self.restored_disk_overlays = None;
+        self.display_fb.clear();
+        self.display_width = 0;
+        self.display_height = 0;
+        // The generation stays monotonic across resets; the cleared cache must be re-rendered.
+        self.display_generation.bump();
+        self.display_presented_generation = None;
+        self.ide_irq14_line = None;
+        self.ide_irq15_line = None;
+        self.serial_irq_lines.clear();
+
+        // Reset chipset lines.
+        self.chipset.a20().set_enabled(false);
+
+        // Rebuild port I/O devices for deterministic power-on state.
+        self.io = IoPortBus::new();
+        self.install_unknown_access_hooks();
+        // Expose the Bochs/QEMU-style debug console port (0xE9) for low-overhead early-boot output.
+        if self.cfg.enable_debugcon {
+            register_debugcon(&mut self.io, self.debugcon_log.clone());
+            machine_events::install_debugcon_tap(
+                &mut self.io,
+                self.debugcon_log.clone(),
+                self.events.clone(),
+            );
+        }
+        self.bench = self.cfg.enable_aero_bench.then(BenchDevice::new);
+
+        let use_legacy_vga = self.cfg.enable_vga && !self.cfg.enable_aerogpu;
+
+        // `enable_vga` controls whether the machine wires the legacy VGA/VBE device model.
+        //
+        // Note: The physical memory bus persists across `Machine::reset()` calls, so any MMIO
+        // mappings that reference the VGA device must keep a stable `Rc` identity across resets.
+        // When VGA is disabled we drop the device handle, but any previously-installed MMIO
+        // mappings (if the machine was ever reset with VGA enabled) will still point at the old
         // instance.
         if use_legacy_vga && !self.cfg.enable_pc_platform {
             // VGA is a special legacy device whose MMIO window lives in the low 1MiB region. The
@@ -10798,33 +14962,58 @@ impl Machine {
                 }
             };
 
+            let generation = self.display_generation.clone();
+            let vga_legacy_perf = self.mmio_perf.vga_legacy.clone();
             self.mem
-                .map_mmio_once(VGA_LEGACY_MMIO_BASE, VGA_LEGACY_MMIO_SIZE, || {
-                    Box::new(VgaLegacyMmioHandler {
-                        base_paddr: VGA_LEGACY_MMIO_BASE as u32,
-                        dev: vga.clone(),
+                .map_mmio_once("vga", VGA_LEGACY_MMIO_BASE, VGA_LEGACY_MMIO_SIZE, || {
+                    Box::new(perf::CountedMmio {
+                        inner: DisplayWriteTracker {
+                            inner: VgaLegacyMmioHandler {
+                                base_paddr: VGA_LEGACY_MMIO_BASE as u32,
+                                dev: vga.clone(),
+                            },
+                            generation: generation.clone(),
+                        },
+                        counter: vga_legacy_perf,
                     })
                 });
 
             // Register VGA ports (attribute/sequencer/graphics/CRTC/DAC + Bochs VBE_DISPI).
-            self.io.register_range(
+            self.io.register_range_with_policy(
                 aero_gpu_vga::VGA_LEGACY_IO_START,
                 aero_gpu_vga::VGA_LEGACY_IO_LEN,
-                Box::new(VgaPortIoDevice { dev: vga.clone() }),
-            );
-            self.io.register_range(
-                aero_gpu_vga::VBE_DISPI_IO_START,
-                aero_gpu_vga::VBE_DISPI_IO_LEN,
-                Box::new(VgaPortIoDevice { dev: vga.clone() }),
+                aero_platform::io::AccessPolicy::Split(aero_platform::io::AccessSizes::BYTE),
+                Box::new(DisplayWriteTracker {
+                    inner: VgaPortIoDevice { dev: vga.clone() },
+                    generation: generation.clone(),
+                }),
             );
+            // The 16-bit INDEX/DATA registers are separate ranges so each word access is aligned.
+            for port in [
+                aero_gpu_vga::VBE_DISPI_INDEX_PORT,
+                aero_gpu_vga::VBE_DISPI_DATA_PORT,
+            ] {
+                self.io.register_range_with_policy(
+                    port,
+                    1,
+                    aero_platform::io::AccessPolicy::Natural(aero_platform::io::AccessSizes::WORD),
+                    Box::new(DisplayWriteTracker {
+                        inner: VgaPortIoDevice { dev: vga.clone() },
+                        generation: generation.clone(),
+                    }),
+                );
+            }
 
             // Map the VBE/SVGA linear framebuffer (LFB) at the VGA device's configured base.
             let (lfb_base, lfb_len) = {
                 let vga = vga.borrow();
                 (u64::from(vga.lfb_base()), vga.vram_size() as u64)
             };
-            self.mem.map_mmio_once(lfb_base, lfb_len, || {
-                Box::new(VgaLfbMmioHandler { dev: vga.clone() })
+            self.mem.map_mmio_once("vga-lfb", lfb_base, lfb_len, || {
+                Box::new(DisplayWriteTracker {
+                    inner: VgaLfbMmioHandler { dev: vga.clone() },
+                    generation,
+                })
             });
         } else if !self.cfg.enable_pc_platform {
             self.vga = None;
@@ -10855,9 +15044,12 @@ impl Machine {
                     ints.clone()
                 }
                 None => {
-                    let ints = Rc::new(RefCell::new(PlatformInterrupts::new_with_cpu_count(
-                        self.cfg.cpu_count,
-                    )));
+                    let ints = if self.cfg.enable_second_ioapic {
+                        PlatformInterrupts::new_with_second_ioapic(self.cfg.cpu_count)
+                    } else {
+                        PlatformInterrupts::new_with_cpu_count(self.cfg.cpu_count)
+                    };
+                    let ints = Rc::new(RefCell::new(ints));
                     self.interrupts = Some(ints.clone());
                     ints
                 }
@@ -10872,12 +15064,57 @@ impl Machine {
                 self.ide_irq15_line = Some(PlatformIrqLine::isa(interrupts.clone(), 15));
             }
 
+            // One line per ISA IRQ carrying enabled COM ports, so shared IRQs are wired-OR.
+            for port in 0..SERIAL_PORT_COUNT {
+                let irq = self.cfg.serial_irqs[port];
+                if Self::serial_port_enabled(&self.cfg, port)
+                    && !self
+                        .serial_irq_lines
+                        .iter()
+                        .any(|(line_irq, _)| *line_irq == irq)
+                {
+                    self.serial_irq_lines
+                        .push((irq, PlatformIrqLine::isa(interrupts.clone(), irq)));
+                }
+            }
+
             PlatformInterrupts::register_imcr_ports(&mut self.io, interrupts.clone());
             register_pic8259_on_platform_interrupts(&mut self.io, interrupts.clone());
 
-            let dma = Rc::new(RefCell::new(Dma8237::new()));
+            let dma = match &self.dma {
+                Some(dma) => {
+                    // Keep registered DMA clients (host wiring) across resets.
+                    dma.borrow_mut().reset();
+                    dma.clone()
+                }
+                None => {
+                    let dma = Rc::new(RefCell::new(Dma8237::new()));
+                    self.dma = Some(dma.clone());
+                    dma
+                }
+            };
             register_dma8237(&mut self.io, dma);
 
+            // Floppy controller (82077AA) on ISA IRQ6 / DMA channel 2. The floppy image is host
+            // state and is re-attached across resets.
+            let mut fdc_dev =
+                FloppyController::new(Box::new(PlatformIrqLine::isa(interrupts.clone(), FDC_IRQ)));
+            if let Some(floppy) = &self.floppy {
+                fdc_dev.attach_media(0, Box::new(floppy.clone()));
+            }
+            let fdc: SharedFloppyController = match &self.fdc {
+                Some(fdc) => {
+                    *fdc.borrow_mut() = fdc_dev;
+                    fdc.clone()
+                }
+                None => {
+                    let fdc: SharedFloppyController = Rc::new(RefCell::new(fdc_dev));
+                    self.fdc = Some(fdc.clone());
+                    fdc
+                }
+            };
+            register_floppy_controller(&mut self.io, fdc);
+
             // PIT 8254.
             let pit: SharedPit8254 = match &self.pit {
                 Some(pit) => {
@@ -10890,11 +15127,15 @@ impl Machine {
                     pit
                 }
             };
-            pit.borrow_mut()
-                .connect_irq0_to_platform_interrupts(interrupts.clone());
+            {
+                let mut pit = pit.borrow_mut();
+                pit.connect_irq0_to_platform_interrupts(interrupts.clone());
+                pit.set_catchup_policy(self.timer_catchup);
+            }
             register_pit8254(&mut self.io, pit.clone());
 
-            // RTC CMOS.
+            // RTC CMOS. A cold reset starts from the persisted NVRAM bank, if any; a warm reset
+            // restores the full device state below.
             let rtc_irq8 = PlatformIrqLine::isa(interrupts.clone(), 8);
             let rtc: SharedRtcCmos<ManualClock, PlatformIrqLine> = match &self.rtc {
                 Some(rtc) => {
@@ -10908,8 +15149,15 @@ impl Machine {
                     rtc
                 }
             };
-            rtc.borrow_mut()
-                .set_memory_size_bytes(self.cfg.ram_size_bytes);
+            if let Some(bank) = &self.pending_cmos_nvram {
+                rtc.borrow_mut().load_nvram_bank(bank);
+            }
+            if let Some(state) = &warm_rtc_state {
+                // The state was produced by the same device model, so restoring it cannot fail.
+                let _ = rtc.borrow_mut().load_state(state);
+                shutdown_status = rtc.borrow_mut().take_shutdown_status();
+            }
+            self.apply_firmware_cmos_fields(&mut rtc.borrow_mut());
             register_rtc_cmos(&mut self.io, rtc.clone());
 
             // ACPI PM. Wire SCI to ISA IRQ9.
@@ -10925,8 +15173,14 @@ impl Machine {
                     let acpi_pm = Rc::new(RefCell::new(AcpiPmIo::new_with_callbacks_and_clock(
                         AcpiPmConfig::default(),
                         AcpiPmCallbacks {
-                            sci_irq: Box::new(PlatformIrqLine::isa(interrupts.clone(), 9)),
-                            request_sleep: None,
+                            sci_irq: Box::new(PlatformIrqLine::isa(
+                                interrupts.clone(),
+                                ACPI_SCI_IRQ,
+                            )),
+                            request_sleep: Some(Box::new({
+                                let latch = self.acpi_sleep_latch.clone();
+                                move |state| latch.set(Some(state))
+                            })),
                             request_power_off: None,
                         },
                         clock.clone(),
@@ -10960,20 +15214,34 @@ impl Machine {
                 // - VGA: 0x3B0..0x3DF (includes both mono and color decode ranges)
                 // - Bochs VBE: aero_gpu_vga::VBE_DISPI_INDEX_PORT (index),
                 //   aero_gpu_vga::VBE_DISPI_DATA_PORT (data)
-                self.io.register_shared_range(
+                self.io.register_shared_range_with_policy(
                     aero_gpu_vga::VGA_LEGACY_IO_START,
                     aero_gpu_vga::VGA_LEGACY_IO_LEN,
+                    aero_platform::io::AccessPolicy::Split(aero_platform::io::AccessSizes::BYTE),
                     {
                         let vga = vga.clone();
-                        move |_port| Box::new(VgaPortIoDevice { dev: vga.clone() })
+                        let generation = self.display_generation.clone();
+                        move |_port| {
+                            Box::new(DisplayWriteTracker {
+                                inner: VgaPortIoDevice { dev: vga.clone() },
+                                generation: generation.clone(),
+                            })
+                        }
                     },
                 );
-                self.io.register_shared_range(
+                self.io.register_shared_range_with_policy(
                     aero_gpu_vga::VBE_DISPI_IO_START,
                     aero_gpu_vga::VBE_DISPI_IO_LEN,
+                    aero_platform::io::AccessPolicy::Natural(aero_platform::io::AccessSizes::WORD),
                     {
                         let vga = vga.clone();
-                        move |_port| Box::new(VgaPortIoDevice { dev: vga.clone() })
+                        let generation = self.display_generation.clone();
+                        move |_port| {
+                            Box::new(DisplayWriteTracker {
+                                inner: VgaPortIoDevice { dev: vga.clone() },
+                                generation: generation.clone(),
+                            })
+                        }
                     },
                 );
 
@@ -10982,12 +15250,20 @@ impl Machine {
                 // directly here.
                 let legacy_base = aero_gpu_vga::VGA_LEGACY_MEM_START as u64;
                 let legacy_len = aero_gpu_vga::VGA_LEGACY_MEM_LEN as u64;
-                self.mem.map_mmio_once(legacy_base, legacy_len, {
+                self.mem.map_mmio_once("vga", legacy_base, legacy_len, {
                     let vga = vga.clone();
+                    let generation = self.display_generation.clone();
+                    let counter = self.mmio_perf.vga_legacy.clone();
                     move || {
-                        Box::new(VgaLegacyMmioHandler {
-                            base_paddr: aero_gpu_vga::VGA_LEGACY_MEM_START,
-                            dev: vga,
+                        Box::new(perf::CountedMmio {
+                            inner: DisplayWriteTracker {
+                                inner: VgaLegacyMmioHandler {
+                                    base_paddr: aero_gpu_vga::VGA_LEGACY_MEM_START,
+                                    dev: vga,
+                                },
+                                generation,
+                            },
+                            counter,
                         })
                     }
                 });
@@ -10998,12 +15274,20 @@ impl Machine {
             if self.cfg.enable_aerogpu {
                 let aerogpu: Rc<RefCell<AeroGpuDevice>> = match &self.aerogpu {
                     Some(dev) => {
-                        dev.borrow_mut().reset();
+                        if warm {
+                            dev.borrow_mut().reset_preserving_vram();
+                        } else {
+                            dev.borrow_mut().reset();
+                            self.aerogpu_vram_generation += 1;
+                        }
                         dev.clone()
                     }
                     None => {
-                        let dev = Rc::new(RefCell::new(AeroGpuDevice::new()));
+                        let dev = Rc::new(RefCell::new(AeroGpuDevice::new(
+                            Self::aerogpu_vram_alloc_size_for_cfg(&self.cfg),
+                        )));
                         self.aerogpu = Some(dev.clone());
+                        self.aerogpu_vram_generation += 1;
                         dev
                     }
                 };
@@ -11012,10 +15296,15 @@ impl Machine {
                         let mut dev = dev.borrow_mut();
                         dev.reset();
                         dev.set_clock(clock.clone());
+                        dev.set_msi_target(Some(Box::new(interrupts.clone())));
                     }
                     None => {
                         let dev = Rc::new(RefCell::new(AeroGpuMmioDevice::default()));
                         dev.borrow_mut().set_clock(clock.clone());
+                        dev.borrow_mut().set_event_log(Some(self.events.clone()));
+                        // MSI sink for when the guest enables MSI-X in PCI config space.
+                        dev.borrow_mut()
+                            .set_msi_target(Some(Box::new(interrupts.clone())));
                         self.aerogpu_mmio = Some(dev);
                     }
                 }
@@ -11024,28 +15313,34 @@ impl Machine {
                 // The PC platform installs a range-based PCI I/O BAR router over most ports, so
                 // legacy VGA ports must be wired as exact per-port mappings to avoid overlapping
                 // range registrations (and to take precedence over the PCI I/O router).
-                self.io.register_shared_range(
+                self.io.register_shared_range_with_policy(
                     aero_gpu_vga::VGA_LEGACY_IO_START,
                     aero_gpu_vga::VGA_LEGACY_IO_LEN,
+                    aero_platform::io::AccessPolicy::Split(aero_platform::io::AccessSizes::BYTE),
                     {
                         let aerogpu = aerogpu.clone();
                         let clock = clock.clone();
+                        let generation = self.display_generation.clone();
                         move |_port| {
                             Box::new(AeroGpuVgaPortWindow {
                                 dev: aerogpu.clone(),
                                 clock: clock.clone(),
+                                generation: generation.clone(),
                             })
                         }
                     },
                 );
-                self.io.register_shared_range(
+                self.io.register_shared_range_with_policy(
                     aero_gpu_vga::VBE_DISPI_IO_START,
                     aero_gpu_vga::VBE_DISPI_IO_LEN,
+                    aero_platform::io::AccessPolicy::Natural(aero_platform::io::AccessSizes::WORD),
                     {
                         let aerogpu = aerogpu.clone();
+                        let generation = self.display_generation.clone();
                         move |_port| {
                             Box::new(AeroGpuVbeDispiPortWindow {
                                 dev: aerogpu.clone(),
+                                generation: generation.clone(),
                             })
                         }
                     },
@@ -11054,19 +15349,28 @@ impl Machine {
                 // Map the legacy VGA memory window (`0xA0000..0xC0000`) as an MMIO overlay that
                 // aliases `VRAM[0..128KiB]`.
                 self.mem.map_mmio_once(
+                    "aerogpu-vga",
                     aero_gpu_vga::VGA_LEGACY_MEM_START as u64,
                     LEGACY_VGA_WINDOW_SIZE as u64,
                     {
                         let aerogpu = aerogpu.clone();
+                        let generation = self.display_generation.clone();
+                        let counter = self.mmio_perf.vga_legacy.clone();
                         move || {
-                            Box::new(AeroGpuLegacyVgaMmio {
-                                dev: aerogpu.clone(),
+                            Box::new(perf::CountedMmio {
+                                inner: AeroGpuLegacyVgaMmio {
+                                    dev: aerogpu.clone(),
+                                    generation,
+                                },
+                                counter,
                             })
                         }
                     },
                 );
             } else {
-                self.aerogpu = None;
+                if self.aerogpu.take().is_some() {
+                    self.aerogpu_vram_generation += 1;
+                }
                 self.aerogpu_mmio = None;
             }
             // PCI config ports (config mechanism #1).
@@ -11106,18 +15410,20 @@ impl Machine {
                 // BAR1 VRAM are reachable via the PCI BAR MMIO router.
                 pci_cfg.borrow_mut().bus_mut().add_device(
                     aero_devices::pci::profile::AEROGPU.bdf,
-                    Box::new(AeroGpuPciConfigDevice::new()),
+                    Box::new(AeroGpuPciConfigDevice::new(
+                        Self::aerogpu_vram_bar_size_for_cfg(&self.cfg),
+                    )),
                 );
             }
             // PCI INTx router.
             let pci_intx: Rc<RefCell<PciIntxRouter>> = match &self.pci_intx {
                 Some(pci_intx) => {
-                    *pci_intx.borrow_mut() = PciIntxRouter::new(PciIntxRouterConfig::default());
+                    *pci_intx.borrow_mut() = PciIntxRouter::new(self.pci_intx_router_config());
                     pci_intx.clone()
                 }
                 None => {
                     let pci_intx = Rc::new(RefCell::new(PciIntxRouter::new(
-                        PciIntxRouterConfig::default(),
+                        self.pci_intx_router_config(),
                     )));
                     self.pci_intx = Some(pci_intx.clone());
                     pci_intx
@@ -11205,10 +15511,10 @@ impl Machine {
             // reliably.
             if self.cfg.enable_ide || self.cfg.enable_uhci {
                 let bdf = aero_devices::pci::profile::ISA_PIIX3.bdf;
-                pci_cfg
-                    .borrow_mut()
-                    .bus_mut()
-                    .add_device(bdf, Box::new(Piix3IsaPciConfigDevice::new()));
+                pci_cfg.borrow_mut().bus_mut().add_device(
+                    bdf,
+                    Box::new(Piix3IsaPciConfigDevice::new(self.cfg.pci_pirq_to_gsi)),
+                );
             }
 
             // When both UHCI and EHCI controllers are enabled, route the first two EHCI root ports
@@ -11272,6 +15578,7 @@ impl Machine {
                         hub.attach_usb2_port_mux(0, mux.clone(), 0);
                         hub.attach_usb2_port_mux(1, mux.clone(), 1);
                     }
+                    self.usb2_mux = Some(mux);
                 }
             }
 
@@ -11286,7 +15593,12 @@ impl Machine {
                         Some(dev.clone())
                     }
                     None => {
-                        let dev = Rc::new(RefCell::new(XhciPciDevice::default()));
+                        let dev = Rc::new(RefCell::new(XhciPciDevice::new_with_controller(
+                            XhciController::with_port_layout(
+                                Self::XHCI_USB2_PORT_COUNT,
+                                Self::XHCI_USB3_PORT_COUNT,
+                            ),
+                        )));
                         dev.borrow_mut()
                             .set_msi_target(Some(Box::new(interrupts.clone())));
                         Some(dev)
@@ -11323,7 +15635,7 @@ impl Machine {
 
                 pci_cfg.borrow_mut().bus_mut().add_device(
                     aero_devices::pci::profile::VIRTIO_NET.bdf,
-                    Box::new(VirtioNetPciConfigDevice::new()),
+                    Box::new(VirtioNetPciConfigDevice::new(self.cfg.virtio_net_transport)),
                 );
 
                 let backend = self.virtio_net.as_ref().and_then(|dev| {
@@ -11336,16 +15648,18 @@ impl Machine {
                     Some(dev) => {
                         // Reset in-place while keeping `Rc` identity stable for persistent MMIO
                         // mappings.
-                        *dev.borrow_mut() = VirtioPciDevice::new(
+                        *dev.borrow_mut() = self.cfg.virtio_net_transport.new_device(
                             Box::new(VirtioNet::new(VirtioNetBackendAdapter::new(backend), mac)),
                             Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
                         );
                         Some(dev.clone())
                     }
-                    None => Some(Rc::new(RefCell::new(VirtioPciDevice::new(
-                        Box::new(VirtioNet::new(VirtioNetBackendAdapter::new(None), mac)),
-                        Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
-                    )))),
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_net_transport.new_device(
+                            Box::new(VirtioNet::new(VirtioNetBackendAdapter::new(None), mac)),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
                 }
             } else {
                 None
@@ -11354,7 +15668,10 @@ impl Machine {
             let virtio_blk = if self.cfg.enable_virtio_blk {
                 pci_cfg.borrow_mut().bus_mut().add_device(
                     aero_devices::pci::profile::VIRTIO_BLK.bdf,
-                    Box::new(VirtioBlkPciConfigDevice::new()),
+                    Box::new(VirtioBlkPciConfigDevice::new(
+                        self.virtio_blk_options().num_queues,
+                        self.cfg.virtio_blk_transport,
+                    )),
                 );
                 match &self.virtio_blk {
                     Some(dev) => {
@@ -11364,10 +15681,15 @@ impl Machine {
                         dev.borrow_mut().reset();
                         Some(dev.clone())
                     }
-                    None => Some(Rc::new(RefCell::new(VirtioPciDevice::new(
-                        Box::new(VirtioBlk::new(Box::new(self.disk.clone()))),
-                        Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
-                    )))),
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_blk_transport.new_device(
+                            Box::new(VirtioBlk::with_options(
+                                Box::new(self.disk.clone()),
+                                self.virtio_blk_options(),
+                            )),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
                 }
             } else {
                 None
@@ -11376,17 +15698,21 @@ impl Machine {
             let virtio_input_keyboard = if self.cfg.enable_virtio_input {
                 pci_cfg.borrow_mut().bus_mut().add_device(
                     aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD.bdf,
-                    Box::new(VirtioInputKeyboardPciConfigDevice::new()),
+                    Box::new(VirtioInputKeyboardPciConfigDevice::new(
+                        self.cfg.virtio_input_transport,
+                    )),
                 );
                 match &self.virtio_input_keyboard {
                     Some(dev) => {
                         dev.borrow_mut().reset();
                         Some(dev.clone())
                     }
-                    None => Some(Rc::new(RefCell::new(VirtioPciDevice::new(
-                        Box::new(VirtioInput::new(VirtioInputDeviceKind::Keyboard)),
-                        Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
-                    )))),
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_input_transport.new_device(
+                            Box::new(VirtioInput::new(VirtioInputDeviceKind::Keyboard)),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
                 }
             } else {
                 None
@@ -11395,17 +15721,21 @@ impl Machine {
             let virtio_input_mouse = if self.cfg.enable_virtio_input {
                 pci_cfg.borrow_mut().bus_mut().add_device(
                     aero_devices::pci::profile::VIRTIO_INPUT_MOUSE.bdf,
-                    Box::new(VirtioInputMousePciConfigDevice::new()),
+                    Box::new(VirtioInputMousePciConfigDevice::new(
+                        self.cfg.virtio_input_transport,
+                    )),
                 );
                 match &self.virtio_input_mouse {
                     Some(dev) => {
                         dev.borrow_mut().reset();
                         Some(dev.clone())
                     }
-                    None => Some(Rc::new(RefCell::new(VirtioPciDevice::new(
-                        Box::new(VirtioInput::new(VirtioInputDeviceKind::Mouse)),
-                        Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
-                    )))),
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_input_transport.new_device(
+                            Box::new(VirtioInput::new(VirtioInputDeviceKind::Mouse)),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
                 }
             } else {
                 None
@@ -11415,22 +15745,98 @@ impl Machine {
                 if self.cfg.enable_virtio_input && self.cfg.enable_virtio_input_tablet {
                     pci_cfg.borrow_mut().bus_mut().add_device(
                         aero_devices::pci::profile::VIRTIO_INPUT_TABLET.bdf,
-                        Box::new(VirtioInputTabletPciConfigDevice::new()),
+                        Box::new(VirtioInputTabletPciConfigDevice::new(
+                            self.cfg.virtio_input_transport,
+                        )),
                     );
                     match &self.virtio_input_tablet {
                         Some(dev) => {
                             dev.borrow_mut().reset();
                             Some(dev.clone())
                         }
-                        None => Some(Rc::new(RefCell::new(VirtioPciDevice::new(
-                            Box::new(VirtioInput::new(VirtioInputDeviceKind::Tablet)),
-                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
-                        )))),
+                        None => Some(Rc::new(RefCell::new(
+                            self.cfg.virtio_input_transport.new_device(
+                                Box::new(VirtioInput::new(VirtioInputDeviceKind::Tablet)),
+                                Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                            ),
+                        ))),
                     }
                 } else {
                     None
                 };
 
+            let virtio_balloon = if self.cfg.enable_virtio_balloon {
+                pci_cfg.borrow_mut().bus_mut().add_device(
+                    aero_devices::pci::profile::VIRTIO_BALLOON.bdf,
+                    Box::new(VirtioBalloonPciConfigDevice::new(
+                        self.cfg.virtio_balloon_transport,
+                    )),
+                );
+                match &self.virtio_balloon {
+                    Some(dev) => {
+                        // The balloon target is host policy and survives the device reset.
+                        dev.borrow_mut().reset();
+                        Some(dev.clone())
+                    }
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_balloon_transport.new_device(
+                            Box::new(VirtioBalloon::new()),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
+                }
+            } else {
+                None
+            };
+
+            let virtio_9p = if self.cfg.enable_virtio_9p {
+                pci_cfg.borrow_mut().bus_mut().add_device(
+                    aero_devices::pci::profile::VIRTIO_9P.bdf,
+                    Box::new(Virtio9pPciConfigDevice::new(self.cfg.virtio_9p_transport)),
+                );
+                match &self.virtio_9p {
+                    Some(dev) => {
+                        // The shared folder backend is host state and survives the device reset.
+                        dev.borrow_mut().reset();
+                        Some(dev.clone())
+                    }
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_9p_transport.new_device(
+                            Box::new(Virtio9p::new(
+                                &self.cfg.virtio_9p_mount_tag,
+                                self.cfg.virtio_9p_read_only,
+                            )),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
+                }
+            } else {
+                None
+            };
+
+            let virtio_rng = if self.cfg.enable_virtio_rng {
+                pci_cfg.borrow_mut().bus_mut().add_device(
+                    aero_devices::pci::profile::VIRTIO_RNG.bdf,
+                    Box::new(VirtioRngPciConfigDevice::new(self.cfg.virtio_rng_transport)),
+                );
+                match &self.virtio_rng {
+                    Some(dev) => {
+                        // The entropy sources (and the deterministic stream position) are host
+                        // state and survive the device reset.
+                        dev.borrow_mut().reset();
+                        Some(dev.clone())
+                    }
+                    None => Some(Rc::new(RefCell::new(
+                        self.cfg.virtio_rng_transport.new_device(
+                            Box::new(VirtioRng::new(self.cfg.rng_seed)),
+                            Box::new(VirtioMsixInterruptSink::new(interrupts.clone())),
+                        ),
+                    ))),
+                }
+            } else {
+                None
+            };
+
             let e1000 = if self.cfg.enable_e1000 {
                 let mac = self.cfg.e1000_mac_addr.unwrap_or(DEFAULT_E1000_MAC_ADDR);
                 pci_cfg.borrow_mut().bus_mut().add_device(
@@ -11438,7 +15844,7 @@ impl Machine {
                     Box::new(E1000PciConfigDevice::new()),
                 );
 
-                match &self.e1000 {
+                let e1000 = match &self.e1000 {
                     Some(e1000) => {
                         // Reset in-place while keeping the `Rc` identity stable for any persistent
                         // MMIO mappings.
@@ -11446,7 +15852,13 @@ impl Machine {
                         Some(e1000.clone())
                     }
                     None => Some(Rc::new(RefCell::new(E1000Device::new(mac)))),
+                };
+                if let Some(e1000) = &e1000 {
+                    // Re-apply the host cable state to the freshly constructed NIC. An unplugged
+                    // cable latches a (masked) LSC cause that drivers clear during init.
+                    e1000.borrow_mut().set_link_up(self.nic_link_up);
                 }
+                e1000
             } else {
                 None
             };
@@ -11566,6 +15978,54 @@ impl Machine {
                 }
             }
 
+            if let Some(virtio_balloon) = virtio_balloon.as_ref() {
+                let bdf = aero_devices::pci::profile::VIRTIO_BALLOON.bdf;
+                let (command, bar0_base) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let cfg = pci_cfg.bus_mut().device_config(bdf);
+                    let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
+                    let bar0_base = cfg.and_then(|cfg| cfg.bar_range(0)).map(|range| range.base);
+                    (command, bar0_base)
+                };
+                let mut dev = virtio_balloon.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+            }
+
+            if let Some(virtio_9p) = virtio_9p.as_ref() {
+                let bdf = aero_devices::pci::profile::VIRTIO_9P.bdf;
+                let (command, bar0_base) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let cfg = pci_cfg.bus_mut().device_config(bdf);
+                    let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
+                    let bar0_base = cfg.and_then(|cfg| cfg.bar_range(0)).map(|range| range.base);
+                    (command, bar0_base)
+                };
+                let mut dev = virtio_9p.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+            }
+
+            if let Some(virtio_rng) = virtio_rng.as_ref() {
+                let bdf = aero_devices::pci::profile::VIRTIO_RNG.bdf;
+                let (command, bar0_base) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let cfg = pci_cfg.bus_mut().device_config(bdf);
+                    let command = cfg.map(|cfg| cfg.command()).unwrap_or(0);
+                    let bar0_base = cfg.and_then(|cfg| cfg.bar_range(0)).map(|range| range.base);
+                    (command, bar0_base)
+                };
+                let mut dev = virtio_rng.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+            }
+
             if let Some(xhci) = xhci.as_ref() {
                 let bdf = aero_devices::pci::profile::USB_XHCI_QEMU.bdf;
                 let (command, bar0_base, msi_state, msix_state) = {
@@ -11703,136 +16163,176 @@ impl Machine {
             let vga = self.vga.clone();
             let aerogpu = self.aerogpu.clone();
             let aerogpu_mmio = self.aerogpu_mmio.clone();
+            let display_generation = self.display_generation.clone();
+            let pci_perf = self.mmio_perf.pci.clone();
             let ehci = ehci.clone();
             let xhci = xhci.clone();
 
             // Map the full ACPI-reported PCI MMIO window so BAR relocation is reflected
             // immediately even when the guest OS programs a BAR outside the allocator's default
             // sub-window.
-            self.mem.map_mmio_once(PCI_MMIO_BASE, PCI_MMIO_SIZE, || {
-                let mut router = PciBarMmioRouter::new(PCI_MMIO_BASE, pci_cfg.clone());
-                if let Some(vga) = vga.clone() {
-                    router.register_handler(
-                        VGA_PCI_BDF,
-                        VGA_PCI_BAR_INDEX,
-                        VgaLfbMmioHandler { dev: vga },
-                    );
-                }
-                if let Some(ahci) = ahci.clone() {
-                    let bdf = aero_devices::pci::profile::SATA_AHCI_ICH9.bdf;
-                    router.register_handler(
-                        bdf,
-                        aero_devices::pci::profile::AHCI_ABAR_BAR_INDEX,
-                        PciConfigSyncedMmioBar::new(
-                            pci_cfg.clone(),
-                            ahci,
+            self.mem
+                .map_mmio_once("pci-mmio-window", PCI_MMIO_BASE, PCI_MMIO_SIZE, || {
+                    let mut router = PciBarMmioRouter::new(PCI_MMIO_BASE, pci_cfg.clone());
+                    if let Some(vga) = vga.clone() {
+                        router.register_handler(
+                            VGA_PCI_BDF,
+                            VGA_PCI_BAR_INDEX,
+                            DisplayWriteTracker {
+                                inner: VgaLfbMmioHandler { dev: vga },
+                                generation: display_generation.clone(),
+                            },
+                        );
+                    }
+                    if let Some(ahci) = ahci.clone() {
+                        let bdf = aero_devices::pci::profile::SATA_AHCI_ICH9.bdf;
+                        router.register_handler(
                             bdf,
                             aero_devices::pci::profile::AHCI_ABAR_BAR_INDEX,
-                        ),
-                    );
-                }
-                if let Some(nvme) = nvme.clone() {
-                    let bdf = aero_devices::pci::profile::NVME_CONTROLLER.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        PciConfigSyncedMmioBar::new(pci_cfg.clone(), nvme, bdf, 0),
-                    );
-                }
-                if let Some(ehci) = ehci.clone() {
-                    let bdf = aero_devices::pci::profile::USB_EHCI_ICH9.bdf;
-                    router.register_handler(
-                        bdf,
-                        EhciPciDevice::MMIO_BAR_INDEX,
-                        PciConfigSyncedMmioBar::new(
-                            pci_cfg.clone(),
-                            ehci,
+                            PciConfigSyncedMmioBar::new(
+                                pci_cfg.clone(),
+                                ahci,
+                                bdf,
+                                aero_devices::pci::profile::AHCI_ABAR_BAR_INDEX,
+                            ),
+                        );
+                    }
+                    if let Some(nvme) = nvme.clone() {
+                        let bdf = aero_devices::pci::profile::NVME_CONTROLLER.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            PciConfigSyncedMmioBar::new(pci_cfg.clone(), nvme, bdf, 0),
+                        );
+                    }
+                    if let Some(ehci) = ehci.clone() {
+                        let bdf = aero_devices::pci::profile::USB_EHCI_ICH9.bdf;
+                        router.register_handler(
                             bdf,
                             EhciPciDevice::MMIO_BAR_INDEX,
-                        ),
-                    );
-                }
-                if let Some(xhci) = xhci.clone() {
-                    let bdf = aero_devices::pci::profile::USB_XHCI_QEMU.bdf;
-                    router.register_handler(
-                        bdf,
-                        XhciPciDevice::MMIO_BAR_INDEX,
-                        PciConfigSyncedMmioBar::new(
-                            pci_cfg.clone(),
-                            xhci,
+                            PciConfigSyncedMmioBar::new(
+                                pci_cfg.clone(),
+                                ehci,
+                                bdf,
+                                EhciPciDevice::MMIO_BAR_INDEX,
+                            ),
+                        );
+                    }
+                    if let Some(xhci) = xhci.clone() {
+                        let bdf = aero_devices::pci::profile::USB_XHCI_QEMU.bdf;
+                        router.register_handler(
                             bdf,
                             XhciPciDevice::MMIO_BAR_INDEX,
-                        ),
-                    );
-                }
-                if let Some(aerogpu) = aerogpu.clone() {
-                    let bdf = aero_devices::pci::profile::AEROGPU.bdf;
-                    router.register_handler(
-                        bdf,
-                        aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
-                        AeroGpuBar1Mmio { dev: aerogpu },
-                    );
-                }
-                if let Some(e1000) = e1000.clone() {
-                    router.register_shared_handler(
-                        aero_devices::pci::profile::NIC_E1000_82540EM.bdf,
-                        0,
-                        e1000,
-                    );
-                }
-                if let Some(virtio_net) = virtio_net.clone() {
-                    let bdf = aero_devices::pci::profile::VIRTIO_NET.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_net, bdf),
-                    );
-                }
-                if let Some(virtio_blk) = virtio_blk.clone() {
-                    let bdf = aero_devices::pci::profile::VIRTIO_BLK.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_blk, bdf),
-                    );
-                }
-                if let Some(virtio_input_keyboard) = virtio_input_keyboard.clone() {
-                    let bdf = aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_keyboard, bdf),
-                    );
-                }
-                if let Some(virtio_input_mouse) = virtio_input_mouse.clone() {
-                    let bdf = aero_devices::pci::profile::VIRTIO_INPUT_MOUSE.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_mouse, bdf),
-                    );
-                }
-                if let Some(virtio_input_tablet) = virtio_input_tablet.clone() {
-                    let bdf = aero_devices::pci::profile::VIRTIO_INPUT_TABLET.bdf;
-                    router.register_handler(
-                        bdf,
-                        0,
-                        VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_tablet, bdf),
-                    );
-                }
-                if let Some(aerogpu_mmio) = aerogpu_mmio.clone() {
-                    router.register_shared_handler(
-                        aero_devices::pci::profile::AEROGPU.bdf,
-                        aero_devices::pci::profile::AEROGPU_BAR0_INDEX,
-                        aerogpu_mmio,
-                    );
-                }
-                Box::new(PciMmioWindow {
-                    window_base: PCI_MMIO_BASE,
-                    router,
-                    legacy_vga_lfb: None,
-                })
-            });
+                            PciConfigSyncedMmioBar::new(
+                                pci_cfg.clone(),
+                                xhci,
+                                bdf,
+                                XhciPciDevice::MMIO_BAR_INDEX,
+                            ),
+                        );
+                    }
+                    if let Some(aerogpu) = aerogpu.clone() {
+                        let bdf = aero_devices::pci::profile::AEROGPU.bdf;
+                        router.register_handler(
+                            bdf,
+                            aero_devices::pci::profile::AEROGPU_BAR1_VRAM_INDEX,
+                            AeroGpuBar1Mmio {
+                                dev: aerogpu,
+                                generation: display_generation.clone(),
+                            },
+                        );
+                    }
+                    if let Some(e1000) = e1000.clone() {
+                        router.register_shared_handler(
+                            aero_devices::pci::profile::NIC_E1000_82540EM.bdf,
+                            0,
+                            e1000,
+                        );
+                    }
+                    if let Some(virtio_net) = virtio_net.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_NET.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_net, bdf),
+                        );
+                    }
+                    if let Some(virtio_blk) = virtio_blk.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_BLK.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_blk, bdf),
+                        );
+                    }
+                    if let Some(virtio_input_keyboard) = virtio_input_keyboard.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_keyboard, bdf),
+                        );
+                    }
+                    if let Some(virtio_input_mouse) = virtio_input_mouse.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_INPUT_MOUSE.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_mouse, bdf),
+                        );
+                    }
+                    if let Some(virtio_input_tablet) = virtio_input_tablet.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_INPUT_TABLET.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_input_tablet, bdf),
+                        );
+                    }
+                    if let Some(virtio_balloon) = virtio_balloon.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_BALLOON.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_balloon, bdf),
+                        );
+                    }
+                    if let Some(virtio_9p) = virtio_9p.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_9P.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_9p, bdf),
+                        );
+                    }
+                    if let Some(virtio_rng) = virtio_rng.clone() {
+                        let bdf = aero_devices::pci::profile::VIRTIO_RNG.bdf;
+                        router.register_handler(
+                            bdf,
+                            0,
+                            VirtioPciBar0Mmio::new(pci_cfg.clone(), virtio_rng, bdf),
+                        );
+                    }
+                    if let Some(aerogpu_mmio) = aerogpu_mmio.clone() {
+                        router.register_handler(
+                            aero_devices::pci::profile::AEROGPU.bdf,
+                            aero_devices::pci::profile::AEROGPU_BAR0_INDEX,
+                            AeroGpuBar0Mmio {
+                                pci_cfg: pci_cfg.clone(),
+                                dev: aerogpu_mmio,
+                                generation: display_generation.clone(),
+                            },
+                        );
+                    }
+                    Box::new(perf::CountedMmio {
+                        inner: PciMmioWindow {
+                            window_base: PCI_MMIO_BASE,
+                            router,
+                            legacy_vga_lfb: None,
+                        },
+                        counter: pci_perf,
+                    })
+                });
 
             // Register dispatchers for PCI I/O BARs allocated by BIOS POST.
             //
@@ -11877,6 +16377,64 @@ impl Machine {
                         E1000PciIoBar { dev: e1000 },
                     );
                 }
+                // Legacy virtio register blocks of transitional devices (BAR2).
+                for (dev, profile, transport) in [
+                    (
+                        &virtio_net,
+                        aero_devices::pci::profile::VIRTIO_NET,
+                        self.cfg.virtio_net_transport,
+                    ),
+                    (
+                        &virtio_blk,
+                        aero_devices::pci::profile::VIRTIO_BLK,
+                        self.cfg.virtio_blk_transport,
+                    ),
+                    (
+                        &virtio_input_keyboard,
+                        aero_devices::pci::profile::VIRTIO_INPUT_KEYBOARD,
+                        self.cfg.virtio_input_transport,
+                    ),
+                    (
+                        &virtio_input_mouse,
+                        aero_devices::pci::profile::VIRTIO_INPUT_MOUSE,
+                        self.cfg.virtio_input_transport,
+                    ),
+                    (
+                        &virtio_input_tablet,
+                        aero_devices::pci::profile::VIRTIO_INPUT_TABLET,
+                        self.cfg.virtio_input_transport,
+                    ),
+                    (
+                        &virtio_balloon,
+                        aero_devices::pci::profile::VIRTIO_BALLOON,
+                        self.cfg.virtio_balloon_transport,
+                    ),
+                    (
+                        &virtio_9p,
+                        aero_devices::pci::profile::VIRTIO_9P,
+                        self.cfg.virtio_9p_transport,
+                    ),
+                    (
+                        &virtio_rng,
+                        aero_devices::pci::profile::VIRTIO_RNG,
+                        self.cfg.virtio_rng_transport,
+                    ),
+                ] {
+                    let Some(dev) = dev.clone() else {
+                        continue;
+                    };
+                    if transport == VirtioPciTransport::Transitional {
+                        router.register_handler(
+                            profile.bdf,
+                            aero_devices::pci::profile::VIRTIO_LEGACY_IO_BAR_INDEX,
+                            VirtioPciLegacyIoBar {
+                                pci_cfg: pci_cfg.clone(),
+                                bdf: profile.bdf,
+                                dev,
+                            },
+                        );
+                    }
+                }
             }
             let io_base = u16::try_from(pci_allocator_cfg.io_base)
                 .expect("PCI I/O BAR base should fit in u16");
@@ -11885,7 +16443,10 @@ impl Machine {
             self.io.register_range(
                 io_base,
                 io_len,
-                Box::new(PciIoBarWindow { router: io_router }),
+                Box::new(PciIoBarWindow {
+                    router: io_router,
+                    unknown_access: self.unknown_access.clone(),
+                }),
             );
 
             // Register IDE legacy I/O ports after BIOS POST so the guest-visible PCI command/BAR
@@ -11906,8 +16467,9 @@ impl Machine {
                         }),
                     );
                 }
-                // Primary control block: 0x3F6..=0x3F7.
-                for port in PRIMARY_PORTS.ctrl_base..PRIMARY_PORTS.ctrl_base + 2 {
+                // Primary control block: 0x3F6. Port 0x3F7 is shared with the floppy controller's
+                // DIR/CCR register; the FDC owns it (as on PIIX3 systems with an onboard FDC).
+                for port in PRIMARY_PORTS.ctrl_base..PRIMARY_PORTS.ctrl_base + 1 {
                     self.io.register(
                         port,
                         Box::new(IdePort {
@@ -11959,9 +16521,14 @@ impl Machine {
             self.virtio_input_keyboard = virtio_input_keyboard;
             self.virtio_input_mouse = virtio_input_mouse;
             self.virtio_input_tablet = virtio_input_tablet;
+            self.virtio_balloon = virtio_balloon;
+            self.virtio_9p = virtio_9p;
+            self.virtio_rng = virtio_rng;
             self.ahci = ahci;
             self.nvme = nvme;
             self.ide = ide;
+            // Controllers created by this reset still need the host's fault injection policy.
+            self.install_storage_fault_injectors(false);
             self.virtio_blk = virtio_blk;
             self.uhci = uhci;
             self.ehci = ehci;
@@ -11969,7 +16536,7 @@ impl Machine {
 
             // If enabled, ensure the canonical "external hub + synthetic HID" USB topology is
             // present immediately after reset.
-            self.ensure_uhci_synthetic_usb_hid_topology();
+            self.ensure_synthetic_usb_hid_topology();
 
             // MMIO mappings persist in the physical bus; ensure the canonical PC regions exist.
             self.map_pc_platform_mmio_regions();
@@ -11978,6 +16545,8 @@ impl Machine {
             self.interrupts = None;
             self.pit = None;
             self.rtc = None;
+            self.dma = None;
+            self.fdc = None;
             self.pci_cfg = None;
             self.pci_intx = None;
             self.acpi_pm = None;
@@ -11994,29 +16563,45 @@ impl Machine {
             self.virtio_net = None;
             self.virtio_input_keyboard = None;
             self.virtio_input_mouse = None;
+            self.virtio_balloon = None;
+            self.virtio_9p = None;
+            self.virtio_rng = None;
             self.ide = None;
             self.virtio_blk = None;
             self.uhci = None;
             self.ehci = None;
+            self.usb2_mux = None;
+            self.usb_proxies.clear();
             self.xhci = None;
-        }
-        if self.cfg.enable_serial {
-            let uart: SharedSerial16550 = Rc::new(RefCell::new(Serial16550::new(0x3F8)));
-            register_serial16550(&mut self.io, uart.clone());
-            self.serial = Some(uart);
-        } else {
-            self.serial = None;
+            self.xhci_attached_ports.clear();
+        }
+        for (port, base) in SERIAL_PORT_BASES.into_iter().enumerate() {
+            self.serial[port] = Self::serial_port_enabled(&self.cfg, port).then(|| {
+                let uart: SharedSerial16550 = Rc::new(RefCell::new(Serial16550::new(base)));
+                register_serial16550(&mut self.io, uart.clone());
+                machine_events::install_serial_tap(
+                    &mut self.io,
+                    port as u8,
+                    base,
+                    uart.clone(),
+                    self.events.clone(),
+                );
+                uart
+            });
         }
 
         if self.cfg.enable_a20_gate {
-            let dev = A20GateDevice::with_reset_sink(self.chipset.a20(), self.reset_latch.clone());
+            let dev = A20GateDevice::with_reset_sink(
+                self.chipset.a20(),
+                self.reset_sink(ResetSource::Port0x92),
+            );
             self.io.register(A20_GATE_PORT, Box::new(dev));
         }
 
         if self.cfg.enable_reset_ctrl {
             self.io.register(
                 RESET_CTRL_PORT,
-                Box::new(ResetCtrl::new(self.reset_latch.clone())),
+                Box::new(ResetCtrl::new(self.reset_sink(ResetSource::Port0xCF9))),
             );
         }
 
@@ -12033,7 +16618,7 @@ impl Machine {
             ctrl.borrow_mut().set_system_control_sink(Box::new(
                 aero_devices::i8042::PlatformSystemControlSink::with_reset_sink(
                     self.chipset.a20(),
-                    self.reset_latch.clone(),
+                    self.reset_sink(ResetSource::KeyboardController),
                 ),
             ));
 
@@ -12042,7 +16627,48 @@ impl Machine {
             self.i8042 = None;
         }
 
-        self.assist = AssistContext::default();
+        // Capture POST codes last so the DMA page register (if present) is wrapped, not replaced.
+        post_code::install_post_code_port(
+            &mut self.io,
+            self.post_codes.clone(),
+            self.events.clone(),
+        );
+
+        if self.cfg.enable_tpm {
+            // Keep the `Rc` identity (and therefore the host-installed backend) stable across
+            // resets; the persistent MMIO mapping refers to it.
+            let tpm = match &self.tpm {
+                Some(tpm) => {
+                    tpm.borrow_mut().reset();
+                    tpm.clone()
+                }
+                None => {
+                    let tpm = Rc::new(RefCell::new(TpmCrb::new(Box::new(NullTpmBackend))));
+                    self.tpm = Some(tpm.clone());
+                    tpm
+                }
+            };
+            self.mem.map_mmio_once(
+                "tpm-crb",
+                tpm::TPM_CRB_MMIO_BASE,
+                tpm::TPM_CRB_MMIO_SIZE,
+                || Box::new(TpmCrbMmio { tpm }),
+            );
+        } else {
+            self.tpm = None;
+        }
+
+        self.reset_assist_context();
+        if let Some(watchdog) = self.hang_watchdog.as_mut() {
+            watchdog.rearm();
+        }
+        if let Some(sampler) = self.dirty_sampler.as_mut() {
+            sampler.rearm();
+        }
+        if let Some(tracker) = self.input_latency.as_mut() {
+            tracker.abandon_pending();
+        }
+        self.event_injector.clear();
         self.cpu = CpuCore::new(CpuMode::Real);
         set_cpu_apic_base_bsp_bit(&mut self.cpu, true);
         // Application processors (APs) start with the BSP bit (IA32_APIC_BASE[8]) cleared.
@@ -12074,7 +16700,14 @@ impl Machine {
         let boot_drive = self.bios.config().boot_drive;
         let cd_boot_drive = self.bios.config().cd_boot_drive;
         let boot_from_cd_if_present = self.bios.config().boot_from_cd_if_present;
+        let boot_order = self.bios.config().boot_order.clone();
+        let smbios = self.bios.config().smbios.clone();
+        let boot_menu_timeout_ms = self.bios.config().boot_menu_timeout_ms;
+        // A boot-menu request is one-shot: it applies to this POST only.
+        let boot_menu = std::mem::take(&mut self.boot_menu_requested);
         self.boot_drive = boot_drive;
+        self.cfg.boot_order = boot_order.clone();
+        self.cfg.smbios = smbios.clone();
         self.cfg.boot_drive = boot_drive;
         self.cfg.boot_device = if (0xE0..=0xEF).contains(&boot_drive) {
             BootDevice::Cdrom
@@ -12092,22 +16725,70 @@ impl Machine {
         // at the PCI MMIO window (where device BARs live) could cause BIOS VBE helpers like
         // `int 0x10, ax=0x4F02` to scribble over PCI device BARs.
         let vbe_lfb_base = use_legacy_vga.then(|| self.legacy_vga_lfb_base());
-        self.bios = Bios::new(BiosConfig {
-            memory_size_bytes: self.cfg.ram_size_bytes,
-            boot_drive,
-            cd_boot_drive,
-            boot_from_cd_if_present,
-            cpu_count: self.cfg.cpu_count,
-            smbios_uuid_seed: self.cfg.smbios_uuid_seed,
-            enable_acpi: self.cfg.enable_pc_platform && self.cfg.enable_acpi,
-            vbe_lfb_base,
-            ..Default::default()
-        });
+        // A shutdown-status resume continues with the firmware state of the interrupted boot (E820
+        // map, PCI device list, video state), so the BIOS is only rebuilt when POST runs.
+        let resume = run_post && warm && firmware::bios::is_shutdown_resume_status(shutdown_status);
+        if !resume {
+            // EDD 3.0 device paths (INT 13h AH=48h) name the controllers that back the BIOS drives:
+            // the shared disk on AHCI port 0 and the install media on the IDE secondary master.
+            let edd_path = |bdf: PciBdf, interface| EddDevicePath {
+                bus: bdf.bus,
+                device: bdf.device,
+                function: bdf.function,
+                interface,
+            };
+            let hdd_device_path = (self.cfg.enable_ahci && self.ahci_port0_auto_attach_shared_disk)
+                .then(|| {
+                    edd_path(
+                        aero_devices::pci::profile::SATA_AHCI_ICH9.bdf,
+                        EddInterface::Sata { port: 0 },
+                    )
+                });
+            let cdrom_device_path = self.cfg.enable_ide.then(|| {
+                edd_path(
+                    aero_devices::pci::profile::IDE_PIIX3.bdf,
+                    EddInterface::Atapi {
+                        channel: 1,
+                        slave: false,
+                    },
+                )
+            });
+            self.bios = Bios::new(BiosConfig {
+                memory_size_bytes: self.cfg.ram_size_bytes,
+                boot_drive,
+                cd_boot_drive,
+                boot_from_cd_if_present,
+                boot_order,
+                boot_menu,
+                boot_menu_timeout_ms,
+                cpu_count: self.cfg.cpu_count,
+                smbios_uuid_seed: self.cfg.smbios_uuid_seed,
+                smbios,
+                enable_acpi: self.cfg.enable_pc_platform && self.cfg.enable_acpi,
+                tpm_crb_base: self.cfg.enable_tpm.then_some(tpm::TPM_CRB_MMIO_BASE),
+                numa_nodes: self.cfg.numa_nodes.clone().unwrap_or_default(),
+                vbe_lfb_base,
+                serial_ports: (0..SERIAL_PORT_COUNT)
+                    .filter(|&port| Self::serial_port_enabled(&self.cfg, port))
+                    .map(|port| SERIAL_PORT_BASES[port])
+                    .collect(),
+                // ACPI `_PRT` and the PCI interrupt lines must agree with the power-on value of
+                // the PIIX3 PIRQ route registers (see `Piix3IsaPciConfigDevice`).
+                pirq_to_gsi: self.cfg.pci_pirq_to_gsi,
+                second_io_apic_base: self
+                    .cfg
+                    .enable_second_ioapic
+                    .then_some(SECOND_IOAPIC_MMIO_BASE as u32),
+                hdd_device_path,
+                cdrom_device_path,
+                ..Default::default()
+            });
+        }
         // Patch the BIOS's VBE controller `TotalMemory` reporting when the active framebuffer is
         // backed by a device-owned VRAM aperture (e.g. AeroGPU BAR1) rather than the firmware test
         // default in guest RAM.
         if self.cfg.enable_aerogpu {
-            let blocks = aero_devices::pci::profile::AEROGPU_VRAM_SIZE.div_ceil(64 * 1024);
+            let blocks = self.aerogpu_vram_bar_size().div_ceil(64 * 1024);
             self.bios.video.vbe.total_memory_64kb_blocks = blocks.min(u64::from(u16::MAX)) as u16;
         } else if use_legacy_vga {
             let vram_bytes = self
@@ -12119,39 +16800,107 @@ impl Machine {
             self.bios.video.vbe.total_memory_64kb_blocks = blocks.min(u64::from(u16::MAX)) as u16;
         }
 
-        let bus: &mut dyn BiosBus = &mut self.mem;
-        // Optional ISO install media: expose it to the BIOS as a CD-ROM backend (2048-byte
-        // sectors), alongside the primary HDD BlockDevice.
-        let mut cdrom = self.install_media.as_ref().and_then(InstallMedia::upgrade);
-        let cdrom_ref = cdrom
-            .as_mut()
-            .map(|cdrom| cdrom as &mut dyn firmware::bios::CdromDevice);
-
-        if let Some(pci_cfg) = &self.pci_cfg {
-            let mut pci = SharedPciConfigPortsBiosAdapter::new(pci_cfg.clone());
-            self.bios.post_with_pci(
+        // A shutdown-status resume continues the interrupted boot; anything else starts a new one.
+        if !resume {
+            self.boot_stage.reset();
+        }
+        if resume {
+            let mut pci = self
+                .pci_cfg
+                .clone()
+                .map(SharedPciConfigPortsBiosAdapter::new);
+            self.bios.resume_from_shutdown(
                 &mut self.cpu.state,
-                bus,
-                &mut self.disk,
-                cdrom_ref,
-                Some(&mut pci),
+                &mut self.mem,
+                pci.as_mut()
+                    .map(|pci| pci as &mut dyn firmware::bios::PciConfigSpace),
+                shutdown_status,
             );
-        } else {
-            self.bios
-                .post(&mut self.cpu.state, bus, &mut self.disk, cdrom_ref);
+            self.drain_bios_post_codes();
+        } else if run_post {
+            // Optional ISO install media: expose it to the BIOS as a CD-ROM backend (2048-byte
+            // sectors), alongside the primary HDD BlockDevice.
+            let mut cdrom = self.install_media.as_ref().and_then(InstallMedia::upgrade);
+
+            // The host network backend (if any) backs PXE network boot entries while POST runs.
+            // It is only borrowed when POST may boot from it; the boot menu just lists a present
+            // NIC and hands it back before any selection is made (see `poll_boot_menu`).
+            let network_boot = self.bios.config().boot_menu
+                || self
+                    .bios
+                    .effective_boot_order(cdrom.is_some())
+                    .contains(&firmware::bios::BootEntry::Network);
+            let mut nic = if network_boot {
+                self.take_pxe_nic()
+            } else {
+                None
+            };
+            let nic_ref = nic
+                .as_mut()
+                .map(|nic| nic as &mut dyn firmware::bios::PxeNic);
+
+            let bus: &mut dyn BiosBus = &mut self.mem;
+            let cdrom_ref = cdrom
+                .as_mut()
+                .map(|cdrom| cdrom as &mut dyn firmware::bios::CdromDevice);
+            // The attached floppy image (if any) backs drive A: for floppy boot entries.
+            let mut floppy = self.floppy.clone();
+            let floppy_ref = floppy
+                .as_mut()
+                .map(|floppy| floppy as &mut dyn firmware::bios::BlockDevice);
+
+            if let Some(pci_cfg) = &self.pci_cfg {
+                let mut pci = SharedPciConfigPortsBiosAdapter::new(pci_cfg.clone());
+                self.bios.post_with_devices(
+                    &mut self.cpu.state,
+                    bus,
+                    &mut self.disk,
+                    floppy_ref,
+                    cdrom_ref,
+                    nic_ref,
+                    Some(&mut pci),
+                );
+            } else {
+                self.bios.post_with_devices(
+                    &mut self.cpu.state,
+                    bus,
+                    &mut self.disk,
+                    floppy_ref,
+                    cdrom_ref,
+                    nic_ref,
+                    None,
+                );
+            }
+            self.return_pxe_nic(nic);
+            self.drain_bios_post_codes();
+            // The firmware's BDA initialization derives the "fixed disk count" (0x40:0x75) from the
+            // configured boot drive number. When booting via El Torito (`DL=0xE0..=0xEF`), the firmware
+            // sets this count to 0 to avoid inflating it based on the CD drive number.
+            //
+            // In the canonical machine, however, we always expose HDD0 at `DL=0x80` *in addition to*
+            // any CD boot device. Patch the BDA so BIOS INT 13h `drive_present()` checks can still
+            // succeed for HDD accesses while booting from CD.
+            self.mem.write_u8(firmware::bios::BDA_BASE + 0x75, 1);
+            // The firmware only advertises floppy drives when booting from one. Report drive A:
+            // whenever a floppy image is attached so INT 11h/INT 13h see it.
+            if self.floppy.is_some() {
+                self.report_floppy_drive_in_bda();
+            }
+            self.advance_boot_stage(BootStage::PostComplete);
         }
-        // The firmware's BDA initialization derives the "fixed disk count" (0x40:0x75) from the
-        // configured boot drive number. When booting via El Torito (`DL=0xE0..=0xEF`), the firmware
-        // sets this count to 0 to avoid inflating it based on the CD drive number.
-        //
-        // In the canonical machine, however, we always expose HDD0 at `DL=0x80` *in addition to*
-        // any CD boot device. Patch the BDA so BIOS INT 13h `drive_present()` checks can still
-        // succeed for HDD accesses while booting from CD.
-        self.mem.write_u8(firmware::bios::BDA_BASE + 0x75, 1);
 
         // Keep the BIOS VBE LFB base coherent with the machine's active display wiring (legacy
         // VGA PCI BAR assignment or AeroGPU BAR1-derived base).
         self.sync_bios_vbe_lfb_base_to_display_wiring();
+        // The map INT 15h E820/E801/88h report is derived from the BIOS memory map; no MMIO
+        // decoder on the bus may sit inside a range it calls usable RAM.
+        if run_post {
+            debug_assert!(
+                self.e820_ram_mmio_overlaps().is_empty(),
+                "E820 reports RAM over MMIO mappings: {:?}",
+                self.e820_ram_mmio_overlaps()
+            );
+        }
         // The HLE BIOS maintains its own copy of the VBE palette for INT 10h AX=4F09 services, but
         // does not perform VGA port I/O. Keep the AeroGPU-emulated VGA DAC palette coherent with
         // the BIOS palette so 8bpp VBE modes (which are palette-indexed) have a sensible default
@@ -12160,37 +16909,21 @@ impl Machine {
         // This also keeps DAC port reads (`0x3C7/0x3C9`) coherent with BIOS-driven palette changes
         // during early boot.
         if let Some(aerogpu) = &self.aerogpu {
-            let bits = self.bios.video.vbe.dac_width_bits;
-            let pal = &self.bios.video.vbe.palette;
-            let mut dev = aerogpu.borrow_mut();
-            dev.vga_port_write_u8(0x3C8, 0); // set DAC write index
-            for idx in 0..256usize {
-                let base = idx * 4;
-                let b = pal[base];
-                let g = pal[base + 1];
-                let r = pal[base + 2];
-
-                let (r, g, b) = if bits >= 8 {
-                    (r >> 2, g >> 2, b >> 2)
-                } else {
-                    (r & 0x3F, g & 0x3F, b & 0x3F)
-                };
-
-                // VGA DAC write order is R, G, B.
-                dev.vga_port_write_u8(0x3C9, r);
-                dev.vga_port_write_u8(0x3C9, g);
-                dev.vga_port_write_u8(0x3C9, b);
-            }
+            aerogpu.borrow_mut().dac.program_bgr0_entries(
+                0,
+                &self.bios.video.vbe.palette,
+                self.bios.video.vbe.dac_width_bits,
+            );
         }
         self.cpu.state.a20_enabled = self.chipset.a20().enabled();
-        if self.bios.video.vbe.current_mode.is_none() {
+        if run_post && self.bios.video.vbe.current_mode.is_none() {
             self.sync_text_mode_cursor_bda_to_vga_crtc();
         }
 
         // Reset returns the machine to legacy text mode; publish this so external presentation
         // layers can follow (and so any previous WDDM claim is cleared on reset).
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
-        if let Some(scanout_state) = &self.scanout_state {
+        if let Some(scanout_state) = self.guest_scanout_state() {
             let _ = scanout_state.try_publish(ScanoutStateUpdate {
                 source: SCANOUT_SOURCE_LEGACY_TEXT,
                 base_paddr_lo: 0,
@@ -12199,6 +16932,8 @@ impl Machine {
                 height: 0,
                 pitch_bytes: 0,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             });
         }
 
@@ -12233,6 +16968,7 @@ impl Machine {
     ///
     /// This mirrors the behaviour of [`aero_pc_platform::PcPlatform::process_ahci`].
     pub fn process_ahci(&mut self) {
+        let now_ns = self.guest_now_ns();
         let (Some(ahci), Some(pci_cfg)) = (&self.ahci, &self.pci_cfg) else {
             return;
         };
@@ -12276,8 +17012,9 @@ impl Machine {
             }
         }
 
+        dev.set_time_ns(now_ns);
         if bus_master_enabled {
-            dev.process(&mut self.mem);
+            dev.process(&mut self.mem.bus_master());
         }
 
         // Mirror device-managed MSI pending bits back into the canonical PCI config space so
@@ -12301,6 +17038,7 @@ impl Machine {
     ///
     /// This mirrors the behaviour of [`aero_pc_platform::PcPlatform::process_ide`].
     pub fn process_ide(&mut self) {
+        let now_ns = self.guest_now_ns();
         let (Some(ide), Some(pci_cfg)) = (&self.ide, &self.pci_cfg) else {
             return;
         };
@@ -12320,11 +17058,35 @@ impl Machine {
             dev.config_mut().set_bar_base(4, bar4_base);
         }
 
-        dev.tick(&mut self.mem);
+        dev.controller.set_time_ns(now_ns);
+        dev.tick(&mut self.mem.bus_master());
+    }
+
+    /// Run pending ISA DMA transfers for clients registered via
+    /// [`Machine::register_isa_dma_client`].
+    pub fn process_isa_dma(&mut self) {
+        // Bound the work per poll so a client that keeps DREQ asserted cannot stall the CPU.
+        const MAX_ISA_DMA_UNITS_PER_POLL: usize = 4096;
+
+        let Some(dma) = &self.dma else {
+            return;
+        };
+        dma.borrow_mut()
+            .service(&mut self.mem.bus_master(), MAX_ISA_DMA_UNITS_PER_POLL);
+    }
+
+    /// Allow the floppy controller (if present) to make forward progress (ISA DMA channel 2).
+    pub fn process_fdc(&mut self) {
+        let (Some(fdc), Some(dma)) = (&self.fdc, &self.dma) else {
+            return;
+        };
+        fdc.borrow_mut()
+            .tick(&mut dma.borrow_mut(), &mut self.mem.bus_master());
     }
 
     /// Allow the NVMe controller (if present) to make forward progress (DMA).
     pub fn process_nvme(&mut self) {
+        let now_ns = self.guest_now_ns();
         let (Some(nvme), Some(pci_cfg)) = (&self.nvme, &self.pci_cfg) else {
             return;
         };
@@ -12366,7 +17128,8 @@ impl Machine {
                 sync_msix_capability_into_config(cfg, enabled, function_masked);
             }
         }
-        dev.process(&mut self.mem);
+        dev.controller_mut().set_time_ns(now_ns);
+        dev.process(&mut self.mem.bus_master());
 
         // Mirror device-managed MSI pending bits back into the canonical PCI config space so guest
         // config reads observe them. The canonical config space cannot infer pending bits on its
@@ -12407,14 +17170,14 @@ impl Machine {
         if let Some(now_ns) = platform_now_ns {
             dev.tick_vblank(now_ns);
         }
-        dev.process(&mut self.mem);
+        dev.process(&mut self.mem.bus_master());
 
         // Publish WDDM scanout state updates based on BAR0 scanout registers.
         //
         // This is gated behind builds that support publishing into the shared scanout header:
         // native builds and the wasm32 `wasm-threaded` build.
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
-        if let Some(scanout_state) = &self.scanout_state {
+        if let Some(scanout_state) = self.guest_scanout_state() {
             let legacy_text = ScanoutStateUpdate {
                 source: SCANOUT_SOURCE_LEGACY_TEXT,
                 base_paddr_lo: 0,
@@ -12423,6 +17186,8 @@ impl Machine {
                 height: 0,
                 pitch_bytes: 0,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             };
 
             let current_legacy_scanout_descriptor = || -> ScanoutStateUpdate {
@@ -12475,6 +17240,8 @@ impl Machine {
                             height: u32::from(mode_info.height),
                             pitch_bytes: pitch as u32,
                             format,
+                            slot0_offset: 0,
+                            slot1_offset: 0,
                         };
                     }
                     return legacy_text;
@@ -12527,6 +17294,8 @@ impl Machine {
                     height,
                     pitch_bytes: pitch as u32,
                     format,
+                    slot0_offset: 0,
+                    slot1_offset: 0,
                 }
             };
 
@@ -12576,6 +17345,12 @@ impl Machine {
                 let _ = cursor_state.try_publish(update);
             }
         }
+
+        let wddm_scanout_claimed = dev.scanout0_state().wddm_scanout_active;
+        drop(dev);
+        if wddm_scanout_claimed {
+            self.advance_boot_stage(BootStage::WddmScanout);
+        }
     }
 
     /// Drain newly-decoded AeroGPU submissions.
@@ -12599,6 +17374,7 @@ impl Machine {
             return;
         };
         aerogpu.borrow_mut().enable_submission_bridge();
+        self.refresh_paravirt_info();
     }
 
     /// Mark an AeroGPU submission fence as completed by an out-of-process executor.
@@ -12609,7 +17385,7 @@ impl Machine {
 
         let mut dev = aerogpu.borrow_mut();
         let _command = sync_aerogpu_pci_state_into_mmio(pci_cfg, &mut dev);
-        dev.complete_fence_from_backend(&mut self.mem, fence);
+        dev.complete_fence_from_backend(&mut self.mem.bus_master(), fence);
     }
 
     /// Allow the virtio-blk controller (if present) to make forward progress (DMA).
@@ -12645,8 +17421,13 @@ impl Machine {
             return;
         }
 
+        let now_ns = self.guest_now_ns();
+        let mut virtio_blk = virtio_blk.borrow_mut();
+        if let Some(blk) = virtio_blk.device_mut::<VirtioBlk>() {
+            blk.set_time_ns(now_ns);
+        }
         let mut dma = VirtioDmaMemory::new(&mut self.mem);
-        virtio_blk.borrow_mut().process_notified_queues(&mut dma);
+        virtio_blk.process_notified_queues(&mut dma);
     }
 
     /// Allow virtio-input devices (if present) to make forward progress (DMA).
@@ -12676,6 +17457,7 @@ impl Machine {
                 &pci_cfg,
             );
         }
+        self.observe_input_consumption();
     }
 
     fn process_virtio_input_device(
@@ -12722,12 +17504,183 @@ impl Machine {
         virtio.poll_bounded(&mut dma, 0);
     }
 
+    /// Allow the virtio-balloon device (if present) to make forward progress, releasing the guest
+    /// RAM backing any newly inflated pages.
+    pub fn process_virtio_balloon(&mut self) {
+        let (Some(virtio), Some(pci_cfg)) = (self.virtio_balloon.clone(), self.pci_cfg.clone())
+        else {
+            return;
+        };
+
+        // Same transport plumbing (PCI command/MSI-X sync, BME gating, bounded work) as
+        // virtio-input, including polling so a pending stats request returns the held buffer.
+        self.process_virtio_input_device(
+            &virtio,
+            aero_devices::pci::profile::VIRTIO_BALLOON.bdf,
+            &pci_cfg,
+        );
+
+        let events = match virtio.borrow_mut().device_mut::<VirtioBalloon>() {
+            Some(balloon) => balloon.take_events(),
+            None => return,
+        };
+        let ram = self.mem.bus.ram_mut();
+        for event in events {
+            // Deflated pages need no work: backends repopulate them lazily on the next write.
+            let VirtioBalloonEvent::Inflate(pfns) = event else {
+                continue;
+            };
+            for pfn in pfns {
+                let paddr =
+                    u64::from(pfn) << aero_virtio::devices::balloon::VIRTIO_BALLOON_PFN_SHIFT;
+                // PFNs outside RAM (e.g. a buggy driver) are ignored.
+                let _ = ram.discard(
+                    paddr,
+                    aero_virtio::devices::balloon::VIRTIO_BALLOON_PAGE_SIZE,
+                );
+            }
+        }
+    }
+
+    /// Allow the virtio-9p device (if present) to serve pending guest requests.
+    pub fn process_virtio_9p(&mut self) {
+        let (Some(virtio), Some(pci_cfg)) = (self.virtio_9p.clone(), self.pci_cfg.clone()) else {
+            return;
+        };
+
+        // Same transport plumbing (PCI command/MSI-X sync, BME gating, bounded work) as
+        // virtio-input.
+        self.process_virtio_input_device(
+            &virtio,
+            aero_devices::pci::profile::VIRTIO_9P.bdf,
+            &pci_cfg,
+        );
+    }
+
+    /// Attach the host folder served by the virtio-9p device.
+    ///
+    /// The backend survives machine resets and snapshot restores; replacing it invalidates any
+    /// files the guest currently has open. Returns `false` if no virtio-9p device is present.
+    pub fn set_shared_folder_backend(&mut self, backend: Box<dyn SharedFolderBackend>) -> bool {
+        let Some(virtio) = &self.virtio_9p else {
+            return false;
+        };
+        match virtio.borrow_mut().device_mut::<Virtio9p>() {
+            Some(share) => {
+                share.set_backend(backend);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Allow the virtio-rng device (if present) to fill pending guest requests.
+    ///
+    /// Each call is one tick: at most
+    /// [`aero_virtio::devices::rng::VIRTIO_RNG_DEFAULT_BYTES_PER_TICK`] bytes are handed to the
+    /// guest, and buffers beyond that wait for a later call.
+    pub fn process_virtio_rng(&mut self) {
+        let (Some(virtio), Some(pci_cfg)) = (self.virtio_rng.clone(), self.pci_cfg.clone()) else {
+            return;
+        };
+
+        if let Some(rng) = virtio.borrow_mut().device_mut::<VirtioRng>() {
+            rng.refill_budget();
+        }
+        // Same transport plumbing (PCI command/MSI-X sync, BME gating, bounded work) as
+        // virtio-input, including polling so held buffers are filled from the new budget.
+        self.process_virtio_input_device(
+            &virtio,
+            aero_devices::pci::profile::VIRTIO_RNG.bdf,
+            &pci_cfg,
+        );
+    }
+
+    /// Install a host entropy source for the virtio-rng device (e.g. one backed by
+    /// `crypto.getRandomValues`), replacing the deterministic stream seeded from
+    /// [`MachineConfig::rng_seed`].
+    ///
+    /// Host sources are not replayable: they survive machine resets and snapshot restores, and
+    /// snapshots only record that one was installed. Returns `false` if no virtio-rng device is
+    /// present.
+    pub fn set_entropy_source(&mut self, source: Box<dyn EntropySource>) -> bool {
+        let Some(virtio) = &self.virtio_rng else {
+            return false;
+        };
+        match virtio.borrow_mut().device_mut::<VirtioRng>() {
+            Some(rng) => {
+                rng.set_host_source(Some(source));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the memory balloon target size in bytes (rounded down to 4KiB pages).
+    ///
+    /// The guest driver is notified via a configuration-change interrupt and inflates or deflates
+    /// towards the new target at its own pace. The target persists across guest resets and
+    /// snapshots. Returns `false` if no balloon device is present.
+    pub fn balloon_set_target(&mut self, bytes: u64) -> bool {
+        let Some(virtio) = &self.virtio_balloon else {
+            return false;
+        };
+        let pages = bytes >> aero_virtio::devices::balloon::VIRTIO_BALLOON_PFN_SHIFT;
+        let pages = u32::try_from(pages).unwrap_or(u32::MAX);
+        let mut virtio = virtio.borrow_mut();
+        if let Some(balloon) = virtio.device_mut::<VirtioBalloon>() {
+            balloon.set_num_pages(pages);
+        }
+        virtio.signal_config_interrupt();
+        true
+    }
+
+    /// Returns the current memory balloon status, or `None` if no balloon device is present.
+    ///
+    /// This also asks the guest for fresh memory statistics; they become visible in
+    /// [`BalloonStats::guest`] once the guest driver reports them.
+    pub fn balloon_stats(&mut self) -> Option<BalloonStats> {
+        let virtio = self.virtio_balloon.as_ref()?;
+        let (target_pages, actual_pages, guest) = {
+            let mut virtio = virtio.borrow_mut();
+            let balloon = virtio.device_mut::<VirtioBalloon>()?;
+            balloon.request_stats();
+            (balloon.num_pages(), balloon.actual(), balloon.stats())
+        };
+        let page_size = aero_virtio::devices::balloon::VIRTIO_BALLOON_PAGE_SIZE;
+        Some(BalloonStats {
+            target_bytes: u64::from(target_pages) * page_size,
+            actual_bytes: u64::from(actual_pages) * page_size,
+            resident_ram_bytes: self.mem.bus.ram().resident_bytes(),
+            guest,
+        })
+    }
+
     /// Poll any enabled NIC + host network backend bridge once.
     ///
     /// This is safe to call even when no NIC is enabled; it will no-op.
     pub fn poll_network(&mut self) {
         const MAX_FRAMES_PER_POLL: usize = aero_net_pump::DEFAULT_MAX_FRAMES_PER_POLL;
 
+        // Without a host backend an E1000 with empty rings and queues has nothing to move. (Virtio
+        // queues can hold guest buffers that were posted without a notify, so virtio-net is always
+        // polled.)
+        let e1000_idle = self.network_backend.is_none()
+            && self
+                .e1000
+                .as_ref()
+                .is_some_and(|e1000| !e1000.borrow().has_pending_work());
+        if self
+            .tick_scheduler
+            .skip_idle(TickDevice::Network, e1000_idle)
+        {
+            perf::bump(&mut self.perf.skipped_device_ticks.network);
+            return;
+        }
+        if self.e1000.is_some() || self.virtio_net.is_some() {
+            perf::bump(&mut self.perf.device_ticks.network);
+        }
+
         if let Some(e1000) = &self.e1000 {
             let bdf = aero_devices::pci::profile::NIC_E1000_82540EM.bdf;
             let (command, bar0_base, bar1_base) = self
@@ -12766,143 +17719,883 @@ impl Machine {
                 nic.pci_config_write(0x14, 4, bar1_base);
             }
 
-            // `Option<B>` implements `NetworkBackend`, so when no backend is installed this still
-            // drains guest TX frames (dropping them) while making no forward progress on host RX.
-            tick_e1000(
-                &mut nic,
-                &mut self.mem,
-                &mut self.network_backend,
-                MAX_FRAMES_PER_POLL,
-                MAX_FRAMES_PER_POLL,
-            );
-            return;
+            // `Option<B>` implements `NetworkBackend`, so when no backend is installed this still
+            // drains guest TX frames (dropping them) while making no forward progress on host RX.
+            let counts = tick_e1000_with_counts(
+                &mut nic,
+                &mut self.mem.bus_master(),
+                &mut self.network_backend,
+                MAX_FRAMES_PER_POLL,
+                MAX_FRAMES_PER_POLL,
+            );
+            drop(nic);
+            if counts.tx_frames + counts.rx_frames != 0 {
+                self.advance_boot_stage(BootStage::NetworkActive);
+            }
+            return;
+        }
+
+        let Some(virtio) = &self.virtio_net else {
+            return;
+        };
+
+        // Respect PCI Bus Master Enable (bit 2). Virtio DMA is undefined without it.
+        let bdf = aero_devices::pci::profile::VIRTIO_NET.bdf;
+        let (command, msix_enabled, msix_masked) = self
+            .pci_cfg
+            .as_ref()
+            .and_then(|pci_cfg| {
+                let mut pci_cfg = pci_cfg.borrow_mut();
+                match pci_cfg.bus_mut().device_config(bdf) {
+                    Some(cfg) => {
+                        let msix = cfg.capability::<MsixCapability>();
+                        Some((
+                            cfg.command(),
+                            msix.is_some_and(|msix| msix.enabled()),
+                            msix.is_some_and(|msix| msix.function_masked()),
+                        ))
+                    }
+                    None => None,
+                }
+            })
+            .unwrap_or((0, false, false));
+
+        // Keep the virtio transport's internal PCI command register in sync with the canonical PCI
+        // config space owned by the machine.
+        //
+        // `VirtioPciDevice` gates all guest-memory DMA (virtqueue processing) on COMMAND.BME (bit
+        // 2), so if we don't mirror the command register the device will never make forward
+        // progress even after the guest enables bus mastering via PCI config ports.
+        let mut virtio = virtio.borrow_mut();
+        virtio.set_pci_command(command);
+        sync_virtio_msix_from_platform(&mut virtio, msix_enabled, msix_masked);
+
+        if (command & (1 << 2)) == 0 {
+            return;
+        }
+
+        let mut dma = VirtioDmaMemory::new(&mut self.mem);
+        // Clamp all work so a guest or backend cannot cause unbounded processing within a single
+        // `poll_network()` call.
+        const MAX_CHAINS_PER_QUEUE_PER_POLL: usize = MAX_FRAMES_PER_POLL;
+
+        let frame_count = |virtio: &mut VirtioPciDevice| {
+            virtio
+                .device_mut::<VirtioNet<VirtioNetBackendAdapter>>()
+                .map_or(0, |net| net.backend_mut().frame_count())
+        };
+        let frames_before = frame_count(&mut virtio);
+        tick_virtio_net(
+            &mut virtio,
+            &mut dma,
+            MAX_CHAINS_PER_QUEUE_PER_POLL,
+            MAX_FRAMES_PER_POLL,
+        );
+        let active = frame_count(&mut virtio) != frames_before;
+        drop(virtio);
+        if active {
+            self.advance_boot_stage(BootStage::NetworkActive);
+        }
+    }
+
+    fn run_ap_cpus(&mut self, cfg: &Tier0Config, max_insts: u64) {
+        if self.cfg.cpu_count <= 1 || max_insts == 0 {
+            return;
+        }
+
+        let interrupts = self.interrupts.clone();
+
+        // Run each AP for a bounded number of instructions. This is intentionally a very simple
+        // cooperative scheduler (BSP-driven) that is "good enough" for SMP bring-up tests like
+        // INIT+SIPI.
+        let ap_count = self.ap_cpus.len();
+        for idx in 0..ap_count {
+            // Split the AP array so the currently executing AP (`cpu`) is disjoint from the slices
+            // stored in the per-vCPU memory bus adapter. This avoids aliasing mutable references
+            // while still allowing the bus to deliver INIT/SIPI effects to *other* APs.
+            let (before, rest) = self.ap_cpus.split_at_mut(idx);
+            let Some((cpu, after)) = rest.split_first_mut() else {
+                break;
+            };
+
+            let apic_id = (idx as u8).saturating_add(1);
+
+            if let Some(vector) = self.event_injector.deliver(idx + 1, cpu) {
+                perf::bump(&mut self.perf.interrupt_injections);
+                if let Some(watchdog) = self.hang_watchdog.as_mut() {
+                    watchdog.record_vector(vector);
+                }
+            }
+
+            // Ensure APs can be woken from HLT by timer/IOAPIC delivery.
+            //
+            // The BSP uses `poll_platform_interrupt` to translate the platform interrupt controller
+            // (PIC/IOAPIC+LAPIC) into `cpu.pending.external_interrupts`. Do the same for APs so they
+            // can observe LAPIC timer interrupts and IOAPIC destination routing.
+            const MAX_QUEUED_EXTERNAL_INTERRUPTS: usize = 1;
+            if Self::poll_platform_interrupt_for_apic(
+                interrupts.as_ref(),
+                apic_id,
+                cpu,
+                MAX_QUEUED_EXTERNAL_INTERRUPTS,
+            ) {
+                perf::bump(&mut self.perf.interrupt_injections);
+                if let (Some(watchdog), Some(&vector)) = (
+                    self.hang_watchdog.as_mut(),
+                    cpu.pending.external_interrupts().back(),
+                ) {
+                    watchdog.record_vector(vector);
+                }
+            }
+            if cpu.state.halted {
+                continue;
+            }
+
+            // Keep the core's A20 view coherent with the chipset latch.
+            cpu.state.a20_enabled = self.chipset.a20().enabled();
+            self.events.set_now_ns(guest_now_ns(
+                self.platform_clock.as_ref(),
+                cpu.state.msr.tsc,
+                cpu.time.tsc_hz(),
+            ));
+
+            let code_diagnostics = self
+                .code_diagnostics
+                .is_active()
+                .then_some(&*self.code_diagnostics);
+            if let Some(diagnostics) = code_diagnostics {
+                diagnostics.begin_batch(cpu.state.rip(), &self.mem.dirty);
+            }
+
+            // Wrap the shared `SystemMemory` in the per-vCPU LAPIC routing adapter.
+            let phys = PerCpuSystemMemoryBus::new(
+                apic_id,
+                interrupts.clone(),
+                ApCpus::Split { before, after },
+                &mut self.mem,
+                Some(&self.mmio_perf.lapic),
+            );
+            let mut inner = aero_cpu_core::PagingBus::new_with_io(
+                phys,
+                StrictIoPortBus {
+                    io: &mut self.io,
+                    counts: &mut self.perf.port_io,
+                },
+            );
+            std::mem::swap(&mut self.mmu, inner.mmu_mut());
+            let bench = self.bench.as_mut().map(|dev| BenchPort {
+                dev,
+                clock: self.platform_clock.as_ref(),
+                tsc_hz: cpu.time.tsc_hz(),
+                tsc: cpu.state.msr.tsc,
+            });
+            let mut bus = MachineCpuBus {
+                a20: self.chipset.a20(),
+                reset: self.reset_latch.clone(),
+                inner,
+                bench,
+                unknown_access: &self.unknown_access,
+                code_diagnostics,
+            };
+
+            self.unknown_access.enter_cpu(cpu.state.rip());
+            let batch =
+                run_batch_cpu_core_with_assists(cfg, &mut self.assist, cpu, &mut bus, max_insts);
+            self.unknown_access.leave_cpu();
+            std::mem::swap(&mut self.mmu, bus.inner.mmu_mut());
+            if let Some(diagnostics) = code_diagnostics {
+                diagnostics.finish_batch(&self.mem.dirty, self.cfg.ram_size_bytes);
+            }
+            self.perf.record_instructions(idx + 1, batch.executed);
+            if let BatchExit::Exception(exception) = &batch.exit {
+                self.perf.record_exception(exception);
+            }
+        }
+    }
+
+    /// Run the CPU for at most `max_insts` guest instructions.
+    ///
+    /// If the hang watchdog is armed (see [`Machine::set_hang_watchdog`]), a slice that would
+    /// otherwise end with [`RunExit::Completed`] or [`RunExit::Halted`] may instead report
+    /// [`RunExit::HangSuspected`].
+    pub fn run_slice(&mut self, max_insts: u64) -> RunExit {
+        let exit = self.run_slice_inner(max_insts);
+        self.sample_dirty_pages();
+        match exit {
+            RunExit::Completed { executed } | RunExit::Halted { executed } => self
+                .check_hang_watchdog()
+                .map(|details| RunExit::HangSuspected { details, executed })
+                .unwrap_or(exit),
+            exit => exit,
+        }
+    }
+
+    /// Run the CPU until `guest_ns` nanoseconds of guest time have elapsed.
+    ///
+    /// Execution proceeds in internal `run_slice` batches of adaptive size (see
+    /// [`RUN_BUDGET_MIN_BATCH_INSTS`]) with the elapsed guest time checked between them, so the
+    /// budget is overshot by at most the last batch. Returns [`RunExit::Completed`] once the budget
+    /// has elapsed; any other exit of a batch (e.g. [`RunExit::Halted`]) ends the run early. The
+    /// `executed` count of the returned exit covers the whole run.
+    pub fn run_for(&mut self, guest_ns: u64) -> RunExit {
+        self.run_budgeted(Some(guest_ns), &mut || false)
+    }
+
+    /// Run the CPU until `host_check` returns `true`.
+    ///
+    /// `host_check` is polled before every internal batch (see [`Machine::run_for`]), letting the
+    /// host stop at its own deadline (e.g. an animation-frame boundary) without picking an
+    /// instruction count up front. Returns [`RunExit::Completed`] when the check fires; other exits
+    /// end the run early as for [`Machine::run_for`].
+    pub fn run_until_deadline(&mut self, host_check: &mut dyn FnMut() -> bool) -> RunExit {
+        self.run_budgeted(None, host_check)
+    }
+
+    fn run_budgeted(
+        &mut self,
+        budget_ns: Option<u64>,
+        host_check: &mut dyn FnMut() -> bool,
+    ) -> RunExit {
+        let start_ns = self.guest_now_ns();
+        let mut sizer = run_budget::BatchSizer::new();
+        let mut executed = 0u64;
+        loop {
+            let now_ns = self.guest_now_ns();
+            let remaining_ns = match budget_ns {
+                Some(budget_ns) => {
+                    let elapsed_ns = now_ns.saturating_sub(start_ns);
+                    if elapsed_ns >= budget_ns {
+                        return RunExit::Completed { executed };
+                    }
+                    Some(budget_ns - elapsed_ns)
+                }
+                None => None,
+            };
+            if host_check() {
+                return RunExit::Completed { executed };
+            }
+
+            let batch = sizer.next_batch(remaining_ns, self.cpu.time.tsc_hz());
+            let exit = self.run_slice(batch);
+            executed = executed.saturating_add(exit.executed());
+            if !matches!(exit, RunExit::Completed { .. }) {
+                return exit.with_executed(executed);
+            }
+            // Without a TSC frequency guest time never advances; stop rather than spin forever.
+            if budget_ns.is_some() && self.guest_now_ns() == now_ns {
+                return RunExit::Completed { executed };
+            }
+        }
+    }
+
+    /// Arm (`Some(threshold)`) or disarm (`None`) the guest hang watchdog.
+    ///
+    /// While armed, progress is sampled at `run_slice` boundaries: an injected external interrupt,
+    /// guest port I/O, or the BSP's RIP leaving a [`HANG_WATCHDOG_RIP_WINDOW`]-byte window all count
+    /// as progress, as does the BSP being halted with `IF=1` (an idle loop waiting for a timer).
+    /// When none of these is observed for `threshold` of guest time, `run_slice` returns
+    /// [`RunExit::HangSuspected`] and the watchdog restarts its measurement.
+    ///
+    /// The watchdog is host configuration: it is not snapshotted, and it stays armed across
+    /// [`Machine::reset`] and snapshot restore (restarting its measurement).
+    pub fn set_hang_watchdog(&mut self, threshold: Option<Duration>) {
+        self.hang_watchdog = threshold.map(|threshold| {
+            watchdog::HangWatchdog::new(u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX))
+        });
+    }
+
+    /// Start sampling how fast the guest dirties RAM, closing a sampling interval every
+    /// `interval_guest_ns` of guest time (see [`Machine::dirty_rate_stats`]).
+    ///
+    /// Intervals are closed at `run_slice` boundaries, so each one runs until the first boundary at
+    /// or after its deadline. Sampling uses its own dirty bitmap: it neither consumes nor clears
+    /// the pages that the next [`Machine::take_snapshot_dirty`] will save, and taking a snapshot
+    /// does not reset the current interval. Calling this while already sampling restarts the
+    /// measurement with the new interval.
+    ///
+    /// Sampling is host configuration: it is not snapshotted, and it stays enabled across
+    /// [`Machine::reset`] and snapshot restore (discarding the history collected so far).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval_guest_ns` is zero.
+    pub fn start_dirty_sampling(&mut self, interval_guest_ns: u64) {
+        assert!(
+            interval_guest_ns != 0,
+            "dirty sampling interval must be non-zero"
+        );
+        self.mem.dirty.enable_sampling();
+        self.dirty_sampler = Some(dirty_sampling::DirtySampler::new(interval_guest_ns));
+    }
+
+    /// Stop dirty-page rate sampling and drop its history.
+    pub fn stop_dirty_sampling(&mut self) {
+        self.mem.dirty.disable_sampling();
+        self.dirty_sampler = None;
+    }
+
+    /// Dirty-page rate and working-set estimate over the last [`DIRTY_SAMPLING_HISTORY`] completed
+    /// sampling intervals, or `None` if sampling is not enabled.
+    pub fn dirty_rate_stats(&self) -> Option<DirtyRateStats> {
+        self.dirty_sampler
+            .as_ref()
+            .map(|sampler| sampler.stats(SNAPSHOT_DIRTY_PAGE_SIZE))
+    }
+
+    fn sample_dirty_pages(&mut self) {
+        let now_ns = self.guest_now_ns();
+        let Some(sampler) = self.dirty_sampler.as_mut() else {
+            return;
+        };
+        if !sampler.due(now_ns) {
+            return;
+        }
+        if let Some(bitmap) = self.mem.take_sampled_dirty_bitmap() {
+            sampler.record(now_ns, bitmap);
+        }
+    }
+
+    /// Start recording input latency samples for events injected through
+    /// [`Machine::inject_input_batch`] (see [`Machine::take_input_latency_samples`]).
+    ///
+    /// Each event that queues output in an input device is stamped with its host timestamp from
+    /// the batch and the guest time of delivery. For PS/2 and virtio-input devices the sample is
+    /// completed with the guest time at which the guest consumed the event: its last byte was read
+    /// from port 0x60, or its last event was completed into the virtio-input used ring. Consumption
+    /// is observed after each CPU batch and virtio-input poll. Pointer events held back by
+    /// [`MachineConfig::pointer_coalescing`] are sampled when their frame reaches the device.
+    ///
+    /// At most [`INPUT_LATENCY_HISTORY`] samples are retained; older ones are overwritten.
+    /// Sampling is host configuration: it is not snapshotted and stays enabled across
+    /// [`Machine::reset`] and snapshot restore, where samples still waiting for consumption are
+    /// completed without a consume time.
+    pub fn start_input_latency_sampling(&mut self) {
+        if self.input_latency.is_none() {
+            self.input_latency = Some(input_latency::InputLatencyTracker::new());
+        }
+    }
+
+    /// Stop input latency sampling and drop the retained samples.
+    pub fn stop_input_latency_sampling(&mut self) {
+        self.input_latency = None;
+    }
+
+    /// Remove and return the completed input latency samples, oldest first.
+    ///
+    /// Samples whose event the guest has not consumed yet stay buffered until it does (or until
+    /// they are overwritten). Returns an empty list if sampling is not enabled.
+    pub fn take_input_latency_samples(&mut self) -> Vec<InputLatencySample> {
+        self.input_latency
+            .as_mut()
+            .map_or_else(Vec::new, |tracker| tracker.take_complete())
+    }
+
+    /// Queued (or, with `consumed`, guest-consumed) output counters of every input backend.
+    fn input_latency_counters(&self, consumed: bool) -> input_latency::InputCounters {
+        let mut counters = input_latency::InputCounters::default();
+        if let Some(ctrl) = &self.i8042 {
+            let ctrl = ctrl.borrow();
+            let (keyboard, mouse) = if consumed {
+                (
+                    ctrl.keyboard_output_consumed(),
+                    ctrl.mouse_output_consumed(),
+                )
+            } else {
+                (
+                    ctrl.keyboard().output_bytes_queued(),
+                    ctrl.mouse().output_bytes_queued(),
+                )
+            };
+            counters.set(InputBackend::Ps2Keyboard, keyboard);
+            counters.set(InputBackend::Ps2Mouse, mouse);
+        }
+        for (backend, dev) in [
+            (InputBackend::VirtioKeyboard, &self.virtio_input_keyboard),
+            (InputBackend::VirtioMouse, &self.virtio_input_mouse),
+            (InputBackend::VirtioTablet, &self.virtio_input_tablet),
+        ] {
+            let Some(dev) = dev else {
+                continue;
+            };
+            let dev = dev.borrow();
+            if let Some(input) = dev.device::<VirtioInput>() {
+                let value = if consumed {
+                    input.last_delivered_event()
+                } else {
+                    input.events_queued()
+                };
+                counters.set(backend, value);
+            }
+        }
+        if !consumed {
+            counters.set(InputBackend::Usb, self.usb_input_deliveries);
+        }
+        counters
+    }
+
+    /// Record the deliveries of one `inject_input_batch` event, given its host timestamp and the
+    /// counters before it was processed.
+    fn record_input_latency_event(
+        &mut self,
+        event: Option<(Option<u32>, input_latency::InputCounters)>,
+    ) {
+        let Some((host_ts, before)) = event else {
+            return;
+        };
+        let after = self.input_latency_counters(false);
+        let now_ns = self.guest_now_ns();
+        let pointer_held =
+            !self.virtio_mouse_backlog.is_empty() || !self.ps2_mouse_backlog.is_empty();
+        if let Some(tracker) = self.input_latency.as_mut() {
+            tracker.record(host_ts, now_ns, &before, &after);
+            if pointer_held {
+                tracker.hold_pointer(host_ts);
+            }
+        }
+    }
+
+    /// Record the deliveries of a pointer coalescing backlog flush, given the counters before it.
+    fn record_input_latency_pointer_flush(&mut self, before: Option<input_latency::InputCounters>) {
+        let Some(before) = before else {
+            return;
+        };
+        let after = self.input_latency_counters(false);
+        let now_ns = self.guest_now_ns();
+        let drained = self.virtio_mouse_backlog.is_empty() && self.ps2_mouse_backlog.is_empty();
+        if let Some(tracker) = self.input_latency.as_mut() {
+            let host_ts = tracker.release_pointer(drained);
+            tracker.record(host_ts, now_ns, &before, &after);
+        }
+    }
+
+    fn observe_input_consumption(&mut self) {
+        if !self
+            .input_latency
+            .as_ref()
+            .is_some_and(|tracker| tracker.awaiting_consumption())
+        {
+            return;
+        }
+        let consumed = self.input_latency_counters(true);
+        let now_ns = self.guest_now_ns();
+        if let Some(tracker) = self.input_latency.as_mut() {
+            tracker.observe_consumed(&consumed, now_ns);
+        }
+    }
+
+    /// Set the catch-up policy for periodic timers after a large guest time step, or `None` for
+    /// strict deterministic mode (every elapsed timer period is delivered, as replay and tests
+    /// expect). Defaults to [`TimerCatchupPolicy::default`].
+    ///
+    /// A host that pauses (e.g. a backgrounded browser tab) and then advances guest time in one
+    /// [`Machine::tick_platform`] step would otherwise deliver every missed PIT period at once. Under
+    /// the policy such a step delivers a bounded number of interrupts while the PIT, RTC, HPET and
+    /// LAPIC counters still advance by the full elapsed time.
+    ///
+    /// The policy is host configuration: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_timer_catchup_policy(&mut self, policy: Option<TimerCatchupPolicy>) {
+        self.timer_catchup = policy;
+        if let Some(pit) = &self.pit {
+            pit.borrow_mut().set_catchup_policy(policy);
+        }
+    }
+
+    /// The current periodic timer catch-up policy (`None` in strict deterministic mode).
+    pub fn timer_catchup_policy(&self) -> Option<TimerCatchupPolicy> {
+        self.timer_catchup
+    }
+
+    /// Timekeeping self-test with the default [`TimerCoherenceConfig`].
+    ///
+    /// See [`Machine::verify_timer_coherence_with`].
+    pub fn verify_timer_coherence(&mut self, duration_guest_ns: u64) -> TimerCoherenceReport {
+        self.verify_timer_coherence_with(duration_guest_ns, &TimerCoherenceConfig::default())
+    }
+
+    /// Advance guest time by `duration_guest_ns` and check that every timekeeping source (PIT, ACPI
+    /// PM timer, HPET main counter, per-vCPU TSC, RTC periodic interrupt) ticked at its nominal
+    /// frequency.
+    ///
+    /// Time advances in `cfg.step_ns` increments through the same cycle → platform tick path
+    /// [`Machine::run_slice`] uses while the CPU is halted; no instructions execute. This really
+    /// moves the machine's clocks forward: timer interrupts raised along the way stay pending for
+    /// the guest. Sources the guest has not started (HPET without `ENABLE_CNF`, RTC with periodic
+    /// interrupts off) are listed in [`TimerCoherenceReport::not_running`] instead of being
+    /// measured.
+    pub fn verify_timer_coherence_with(
+        &mut self,
+        duration_guest_ns: u64,
+        cfg: &TimerCoherenceConfig,
+    ) -> TimerCoherenceReport {
+        let tsc_hz = self.cpu.time.tsc_hz();
+        let total_cycles = if duration_guest_ns == 0 || tsc_hz == 0 {
+            0
+        } else {
+            timer_coherence::cycles_for_ns(duration_guest_ns, tsc_hz)
+        };
+        let step_cycles = timer_coherence::cycles_for_ns(cfg.step_ns, tsc_hz);
+
+        let mut probe = timer_coherence::TimerProbe::start(self);
+        let mut remaining = total_cycles;
+        while remaining != 0 {
+            let cycles = remaining.min(step_cycles);
+            self.advance_idle_cycles(cycles);
+            probe.sample_pm_timer(self);
+            remaining -= cycles;
         }
+        probe.finish(self, total_cycles, tsc_hz, cfg)
+    }
 
-        let Some(virtio) = &self.virtio_net else {
-            return;
-        };
+    /// Inject latency and/or failures into the requests of the AHCI, NVMe, IDE and virtio-blk
+    /// controllers, or `None` to stop injecting.
+    ///
+    /// Each controller gets its own [`StorageFaultInjector`] (with its own request count and
+    /// delay generator). Delays are measured in guest time: a delayed request completes on the
+    /// first controller processing step (e.g. in [`Machine::run_slice`]) after guest time has
+    /// passed its due time, so a guest that keeps several AHCI slots, NVMe commands or virtio-blk
+    /// requests in flight sees them outstanding concurrently (and possibly completing out of
+    /// order). Injected failures complete with the error the controller would report for the
+    /// matching backend failure.
+    ///
+    /// Installing a policy resets the counters reported by [`Machine::storage_fault_counters`].
+    /// The policy is host configuration: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_storage_fault_policy(&mut self, policy: Option<StorageFaultPolicy>) {
+        self.storage_fault_policy = policy;
+        self.install_storage_fault_injectors(true);
+    }
 
-        // Respect PCI Bus Master Enable (bit 2). Virtio DMA is undefined without it.
-        let bdf = aero_devices::pci::profile::VIRTIO_NET.bdf;
-        let (command, msix_enabled, msix_masked) = self
-            .pci_cfg
-            .as_ref()
-            .and_then(|pci_cfg| {
-                let mut pci_cfg = pci_cfg.borrow_mut();
-                match pci_cfg.bus_mut().device_config(bdf) {
-                    Some(cfg) => {
-                        let msix = cfg.capability::<MsixCapability>();
-                        Some((
-                            cfg.command(),
-                            msix.is_some_and(|msix| msix.enabled()),
-                            msix.is_some_and(|msix| msix.function_masked()),
-                        ))
-                    }
-                    None => None,
-                }
-            })
-            .unwrap_or((0, false, false));
+    /// The current storage fault injection policy.
+    pub fn storage_fault_policy(&self) -> Option<&StorageFaultPolicy> {
+        self.storage_fault_policy.as_ref()
+    }
 
-        // Keep the virtio transport's internal PCI command register in sync with the canonical PCI
-        // config space owned by the machine.
-        //
-        // `VirtioPciDevice` gates all guest-memory DMA (virtqueue processing) on COMMAND.BME (bit
-        // 2), so if we don't mirror the command register the device will never make forward
-        // progress even after the guest enables bus mastering via PCI config ports.
-        let mut virtio = virtio.borrow_mut();
-        virtio.set_pci_command(command);
-        sync_virtio_msix_from_platform(&mut virtio, msix_enabled, msix_masked);
+    /// Injected delays and failures, summed over the storage controllers since the policy was
+    /// installed.
+    pub fn storage_fault_counters(&self) -> StorageFaultCounters {
+        let mut counters = StorageFaultCounters::default();
+        let mut add = |injector: Option<&StorageFaultInjector>| {
+            if let Some(injector) = injector {
+                counters.accumulate(&injector.counters());
+            }
+        };
+        if let Some(ahci) = &self.ahci {
+            add(ahci.borrow().fault_injector());
+        }
+        if let Some(nvme) = &self.nvme {
+            add(nvme.borrow().controller().fault_injector());
+        }
+        if let Some(ide) = &self.ide {
+            add(ide.borrow().controller.fault_injector());
+        }
+        if let Some(virtio_blk) = &self.virtio_blk {
+            add(virtio_blk
+                .borrow()
+                .device::<VirtioBlk>()
+                .and_then(VirtioBlk::fault_injector));
+        }
+        counters
+    }
 
-        if (command & (1 << 2)) == 0 {
+    /// Give the storage controllers an injector for the current policy. Without `replace`, only
+    /// controllers that do not have one yet are updated.
+    fn install_storage_fault_injectors(&self, replace: bool) {
+        if !replace && self.storage_fault_policy.is_none() {
             return;
         }
+        let policy = self.storage_fault_policy.as_ref();
+        let injector = || policy.map(|policy| StorageFaultInjector::new(policy.clone()));
+        if let Some(ahci) = &self.ahci {
+            let mut ahci = ahci.borrow_mut();
+            if replace || ahci.fault_injector().is_none() {
+                ahci.set_fault_injector(injector());
+            }
+        }
+        if let Some(nvme) = &self.nvme {
+            let mut nvme = nvme.borrow_mut();
+            let ctrl = nvme.controller_mut();
+            if replace || ctrl.fault_injector().is_none() {
+                ctrl.set_fault_injector(injector());
+            }
+        }
+        if let Some(ide) = &self.ide {
+            let mut ide = ide.borrow_mut();
+            if replace || ide.controller.fault_injector().is_none() {
+                ide.controller.set_fault_injector(injector());
+            }
+        }
+        if let Some(virtio_blk) = &self.virtio_blk {
+            let mut virtio_blk = virtio_blk.borrow_mut();
+            if let Some(blk) = virtio_blk.device_mut::<VirtioBlk>() {
+                if replace || blk.fault_injector().is_none() {
+                    blk.set_fault_injector(injector());
+                }
+            }
+        }
+    }
 
-        let mut dma = VirtioDmaMemory::new(&mut self.mem);
-        // Clamp all work so a guest or backend cannot cause unbounded processing within a single
-        // `poll_network()` call.
-        const MAX_CHAINS_PER_QUEUE_PER_POLL: usize = MAX_FRAMES_PER_POLL;
+    /// Configure how guest accesses to unclaimed ports and unmapped physical addresses are handled
+    /// (see [`UnknownAccessConfig`]). Port I/O and MMIO have independent policies and ignore lists.
+    ///
+    /// Only accesses made while a vCPU executes guest code are considered; under
+    /// [`UnknownAccessPolicy::Trap`], `run_slice` returns [`RunExit::UnknownPortIo`] /
+    /// [`RunExit::UnknownMmio`] for the first trapped access once the current Tier-0 batch (at
+    /// most one basic block) retires. The access itself still completes with open-bus semantics.
+    ///
+    /// The configuration is host state: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_unknown_access_config(&mut self, config: UnknownAccessConfig) {
+        self.unknown_access.set_config(config);
+        self.install_unknown_access_hooks();
+    }
 
-        tick_virtio_net(
-            &mut virtio,
-            &mut dma,
-            MAX_CHAINS_PER_QUEUE_PER_POLL,
-            MAX_FRAMES_PER_POLL,
-        );
+    /// The current unknown-access configuration.
+    pub fn unknown_access_config(&self) -> UnknownAccessConfig {
+        self.unknown_access.config()
     }
 
-    fn run_ap_cpus(&mut self, cfg: &Tier0Config, max_insts: u64) {
-        if self.cfg.cpu_count <= 1 || max_insts == 0 {
+    /// Drain the unknown-access log: every distinct `(space, address, size, direction)` recorded
+    /// under [`UnknownAccessPolicy::Log`] or [`UnknownAccessPolicy::Trap`] since the previous call.
+    pub fn take_unknown_access_log(&mut self) -> UnknownAccessLog {
+        self.unknown_access.take_log()
+    }
+
+    /// Enable or disable the ROM-write monitor: every write that lands on a mapped ROM range
+    /// (e.g. the BIOS at `0xF0000`, the VGA option ROM) is recorded with the value and the RIP of
+    /// the writing instruction, deduplicated by address and size. The write itself is still
+    /// dropped.
+    ///
+    /// Writes made outside a vCPU batch (firmware HLE, device DMA) are recorded with no RIP. The
+    /// monitor is host state: it is not snapshotted and survives [`Machine::reset`].
+    pub fn set_rom_write_monitor(&mut self, enabled: bool) {
+        self.code_diagnostics.set_rom_writes_enabled(enabled);
+        if !enabled {
+            self.mem.bus.set_rom_write_hook(None);
             return;
         }
+        let diagnostics = self.code_diagnostics.clone();
+        self.mem
+            .bus
+            .set_rom_write_hook(Some(Box::new(move |paddr: u64, bytes: &[u8]| {
+                diagnostics.record_rom_write(paddr, bytes);
+            })));
+    }
 
-        let interrupts = self.interrupts.clone();
+    /// Drain the ROM-write log (see [`Machine::set_rom_write_monitor`]).
+    pub fn take_rom_write_log(&mut self) -> RomWriteLog {
+        self.code_diagnostics.take_rom_write_log()
+    }
 
-        // Run each AP for a bounded number of instructions. This is intentionally a very simple
-        // cooperative scheduler (BSP-driven) that is "good enough" for SMP bring-up tests like
-        // INIT+SIPI.
-        let ap_count = self.ap_cpus.len();
-        for idx in 0..ap_count {
-            // Split the AP array so the currently executing AP (`cpu`) is disjoint from the slices
-            // stored in the per-vCPU memory bus adapter. This avoids aliasing mutable references
-            // while still allowing the bus to deliver INIT/SIPI effects to *other* APs.
-            let (before, rest) = self.ap_cpus.split_at_mut(idx);
-            let Some((cpu, after)) = rest.split_first_mut() else {
-                break;
-            };
+    /// Enable or disable self-modifying-code detection.
+    ///
+    /// After each vCPU batch, the machine checks whether any RAM page that instructions were
+    /// fetched from during the batch was also written during the same batch, and records such
+    /// pages with the RIPs that executed from them. Writes that change code only for later batches
+    /// are not reported. Like [`Machine::set_rom_write_monitor`], this is host state that survives
+    /// [`Machine::reset`].
+    pub fn set_smc_detection(&mut self, enabled: bool) {
+        self.code_diagnostics.set_smc_enabled(enabled);
+        if enabled {
+            self.mem.dirty.enable_write_log();
+        } else {
+            self.mem.dirty.disable_write_log();
+        }
+    }
 
-            let apic_id = (idx as u8).saturating_add(1);
+    /// Drain the self-modifying-code log (see [`Machine::set_smc_detection`]).
+    pub fn take_smc_log(&mut self) -> SmcLog {
+        self.code_diagnostics.take_smc_log()
+    }
 
-            // Ensure APs can be woken from HLT by timer/IOAPIC delivery.
-            //
-            // The BSP uses `poll_platform_interrupt` to translate the platform interrupt controller
-            // (PIC/IOAPIC+LAPIC) into `cpu.pending.external_interrupts`. Do the same for APs so they
-            // can observe LAPIC timer interrupts and IOAPIC destination routing.
-            const MAX_QUEUED_EXTERNAL_INTERRUPTS: usize = 1;
-            let _ = Self::poll_platform_interrupt_for_apic(
-                interrupts.as_ref(),
-                apic_id,
-                cpu,
-                MAX_QUEUED_EXTERNAL_INTERRUPTS,
-            );
-            if cpu.state.halted {
-                continue;
-            }
+    fn install_unknown_access_hooks(&mut self) {
+        if !self.unknown_access.is_active() {
+            self.io.set_unclaimed_hook(None);
+            self.mem.bus.set_unmapped_access_hook(None);
+            return;
+        }
+        let tracker = self.unknown_access.clone();
+        self.io
+            .set_unclaimed_hook(Some(Box::new(move |port: u16, size: u8, write: bool| {
+                tracker.record(
+                    UnknownAccessSpace::PortIo,
+                    u64::from(port),
+                    u32::from(size),
+                    write,
+                );
+            })));
+        let tracker = self.unknown_access.clone();
+        self.mem.bus.set_unmapped_access_hook(Some(Box::new(
+            move |paddr: u64, len: usize, write: bool| {
+                tracker.record(
+                    UnknownAccessSpace::Mmio,
+                    paddr,
+                    u32::try_from(len).unwrap_or(u32::MAX),
+                    write,
+                );
+            },
+        )));
+    }
 
-            // Keep the core's A20 view coherent with the chipset latch.
-            cpu.state.a20_enabled = self.chipset.a20().enabled();
+    fn take_unknown_access_trap(&self, executed: u64) -> Option<RunExit> {
+        let access = self.unknown_access.take_trap()?;
+        Some(match access.space {
+            UnknownAccessSpace::PortIo => RunExit::UnknownPortIo {
+                port: access.addr as u16,
+                size: access.size as u8,
+                write: access.write,
+                executed,
+            },
+            UnknownAccessSpace::Mmio => RunExit::UnknownMmio {
+                paddr: access.addr,
+                size: access.size,
+                write: access.write,
+                executed,
+            },
+        })
+    }
 
-            // Wrap the shared `SystemMemory` in the per-vCPU LAPIC routing adapter.
-            let phys = PerCpuSystemMemoryBus::new(
-                apic_id,
-                interrupts.clone(),
-                ApCpus::Split { before, after },
-                &mut self.mem,
-            );
-            let mut inner =
-                aero_cpu_core::PagingBus::new_with_io(phys, StrictIoPortBus { io: &mut self.io });
-            std::mem::swap(&mut self.mmu, inner.mmu_mut());
-            let mut bus = MachineCpuBus {
-                a20: self.chipset.a20(),
-                reset: self.reset_latch.clone(),
-                inner,
-            };
+    fn check_hang_watchdog(&mut self) -> Option<HangDetails> {
+        let watchdog = self.hang_watchdog.as_mut()?;
+        let state = &self.cpu.state;
+        let sample = watchdog::ProgressSample {
+            tsc: state.msr.tsc,
+            linear_rip: state.segments.cs.base.wrapping_add(state.rip()),
+            interrupt_injections: self.perf.interrupt_injections,
+            port_io: self
+                .perf
+                .port_io
+                .reads
+                .wrapping_add(self.perf.port_io.writes),
+            idle: state.halted && (state.rflags() & RFLAGS_IF) != 0,
+        };
+        let stalled_ns = watchdog.check(sample, self.cpu.time.tsc_hz())?;
+
+        let vcpus = std::iter::once(&self.cpu)
+            .chain(self.ap_cpus.iter())
+            .map(|cpu| HangVcpuState {
+                rip: cpu.state.rip(),
+                cpl: cpu.state.cpl(),
+                interrupts_enabled: (cpu.state.rflags() & RFLAGS_IF) != 0,
+                halted: cpu.state.halted,
+            })
+            .collect();
+        Some(HangDetails {
+            stalled_ns,
+            vcpus,
+            recent_vectors: watchdog.recent_vectors(),
+        })
+    }
 
-            let _ =
-                run_batch_cpu_core_with_assists(cfg, &mut self.assist, cpu, &mut bus, max_insts);
-            std::mem::swap(&mut self.mmu, bus.inner.mmu_mut());
+    /// Give devices a chance to wake the halted BSP; returns `true` if an interrupt was queued
+    /// for delivery.
+    fn poll_halted_wakeup(&mut self) -> bool {
+        const MAX_QUEUED_EXTERNAL_INTERRUPTS: usize = 1;
+
+        // After advancing timers, poll again so any newly-due timer interrupts are injected into
+        // `cpu.pending.external_interrupts`.
+        //
+        // Only poll after the batch when we are going to re-enter execution within the same
+        // `run_slice` call. This avoids acknowledging interrupts at the end of a slice boundary
+        // (e.g. after an `STI` interrupt shadow expires) when the CPU will not execute another
+        // instruction until the host calls `run_slice` again.
+        //
+        // Process AHCI once more here so guests that issue an AHCI command and then execute `HLT`
+        // can still make DMA progress and be woken by INTx within the same `run_slice` call.
+        //
+        // Note: `poll_platform_interrupt` synchronizes PCI INTx source levels into the platform
+        // interrupt controller before polling, so we do not need an explicit
+        // `sync_pci_intx_sources_to_interrupts` call here.
+        self.process_ide();
+        self.process_fdc();
+        self.process_isa_dma();
+        self.process_ahci();
+        self.process_nvme();
+        self.process_virtio_blk();
+        self.process_aerogpu();
+        self.process_virtio_input();
+        self.process_virtio_balloon();
+        self.process_virtio_9p();
+        self.process_virtio_rng();
+        // Like storage controllers, the guest may have kicked a NIC queue immediately before
+        // executing `HLT` (e.g. E1000 TX descriptor doorbell). Poll the network bridge again here
+        // so the device can complete DMA and raise INTx to wake the halted CPU within the same
+        // `run_slice` call.
+        self.poll_network();
+        if self.poll_platform_interrupt(MAX_QUEUED_EXTERNAL_INTERRUPTS) {
+            return true;
         }
+
+        // When halted, advance platform time so timer interrupts can wake the CPU.
+        self.idle_tick_platform_1ms();
+        self.process_ide();
+        self.process_fdc();
+        self.process_isa_dma();
+        self.process_ahci();
+        self.process_nvme();
+        self.process_virtio_blk();
+        self.process_aerogpu();
+        self.process_virtio_input();
+        self.process_virtio_balloon();
+        self.process_virtio_9p();
+        self.process_virtio_rng();
+        self.poll_network();
+        self.poll_platform_interrupt(MAX_QUEUED_EXTERNAL_INTERRUPTS)
     }
 
-    /// Run the CPU for at most `max_insts` guest instructions.
-    pub fn run_slice(&mut self, max_insts: u64) -> RunExit {
+    /// Whether the BSP has nothing to deliver at its next instruction boundary (no pending
+    /// exception, queued external interrupt or host-injected event).
+    fn bsp_has_no_pending_events(&self) -> bool {
+        !self.cpu.pending.has_pending_event()
+            && self.cpu.pending.external_interrupts().is_empty()
+            && !self.event_injector.has_queued(0)
+    }
+
+    fn run_slice_inner(&mut self, max_insts: u64) -> RunExit {
+        perf::bump(&mut self.perf.run_slice_calls);
+        self.deliver_serial_channel_input();
+        self.flush_pointer_backlogs(self.cfg.pointer_coalescing == PointerCoalescing::PerSlice);
         let mut executed = 0u64;
         // Keep Tier-0 instruction gating coherent with the CPUID surface that assists expose to the
         // guest.
         let cfg = Tier0Config::from_cpuid(&self.assist.features);
         while executed < max_insts {
-            if let Some(kind) = self.reset_latch.take() {
+            if let Some(exit) = self.poll_reset_request(executed) {
+                self.flush_serial();
+                return exit;
+            }
+            if self.poll_power_off() {
+                self.flush_serial();
+                return RunExit::PowerOff { executed };
+            }
+            if self.event_injector.take_smi() {
+                self.flush_serial();
+                return RunExit::SmiUnsupported { executed };
+            }
+
+            // The firmware boot menu parks the CPU until a host-injected key (or its timeout)
+            // selects a boot device; each poll accounts for 1ms of idle time.
+            if self.bios.boot_menu_active() {
+                if self.poll_boot_menu() {
+                    self.idle_tick_platform_1ms();
+                    self.flush_serial();
+                    return RunExit::Halted { executed };
+                }
+                continue;
+            }
+
+            // With every vCPU halted and nothing queued for the BSP, no instruction can run until
+            // a device raises an interrupt: go straight to the idle wakeup poll instead of
+            // spinning through the device/batch loop. APs still poll their LAPICs (and run if
+            // woken) once per iteration, as on the regular path.
+            if self.all_vcpus_halted() && self.bsp_has_no_pending_events() {
+                self.run_ap_cpus(&cfg, max_insts - executed);
+                if let Some(exit) = self.take_unknown_access_trap(executed) {
+                    self.flush_serial();
+                    return exit;
+                }
+                if self.poll_halted_wakeup() {
+                    continue;
+                }
                 self.flush_serial();
-                return RunExit::ResetRequested { kind, executed };
+                return RunExit::Halted { executed };
             }
 
             // Keep the core's A20 view coherent with the chipset latch.
@@ -12917,6 +18610,9 @@ impl Machine {
             self.process_virtio_blk();
             self.process_aerogpu();
             self.process_virtio_input();
+            self.process_virtio_balloon();
+            self.process_virtio_9p();
+            self.process_virtio_rng();
 
             self.poll_network();
             self.process_ahci();
@@ -12924,6 +18620,12 @@ impl Machine {
             self.process_virtio_blk();
             self.process_aerogpu();
             self.process_ide();
+            self.process_fdc();
+            self.process_isa_dma();
+
+            // Host-injected NMIs/exceptions (see `inject_nmi`) take priority over the platform
+            // interrupt controller.
+            self.deliver_injected_event(0);
 
             // Poll the platform interrupt controller (PIC/IOAPIC+LAPIC) and enqueue at most one
             // pending external interrupt vector into the CPU core.
@@ -12955,21 +18657,49 @@ impl Machine {
             // Today `Machine` only executes vCPU0, so the APIC ID is always 0 here; multi-vCPU
             // scheduling can pass the correct APIC ID when additional `CpuCore` instances are
             // introduced.
+            // Boot stages are sampled per batch, so a mode switch and the first ring-0 PCI config
+            // write can land in the same batch; compare against the count from before it.
+            let pci_config_writes_before_batch = self.pci_config_write_count();
+            // Events recorded by device taps during the batch carry its start time.
+            self.events.set_now_ns(self.guest_now_ns());
+            let code_diagnostics = self
+                .code_diagnostics
+                .is_active()
+                .then_some(&*self.code_diagnostics);
+            if let Some(diagnostics) = code_diagnostics {
+                diagnostics.begin_batch(self.cpu.state.rip(), &self.mem.dirty);
+            }
             let phys = PerCpuSystemMemoryBus::new(
                 0,
                 self.interrupts.clone(),
                 ApCpus::All(self.ap_cpus.as_mut_slice()),
                 &mut self.mem,
+                Some(&self.mmio_perf.lapic),
+            );
+            let mut inner = aero_cpu_core::PagingBus::new_with_io(
+                phys,
+                StrictIoPortBus {
+                    io: &mut self.io,
+                    counts: &mut self.perf.port_io,
+                },
             );
-            let mut inner =
-                aero_cpu_core::PagingBus::new_with_io(phys, StrictIoPortBus { io: &mut self.io });
             std::mem::swap(&mut self.mmu, inner.mmu_mut());
+            let bench = self.bench.as_mut().map(|dev| BenchPort {
+                dev,
+                clock: self.platform_clock.as_ref(),
+                tsc_hz: self.cpu.time.tsc_hz(),
+                tsc: self.cpu.state.msr.tsc,
+            });
             let mut bus = MachineCpuBus {
                 a20: self.chipset.a20(),
                 reset: self.reset_latch.clone(),
                 inner,
+                bench,
+                unknown_access: &self.unknown_access,
+                code_diagnostics,
             };
 
+            self.unknown_access.enter_cpu(self.cpu.state.rip());
             let batch = run_batch_cpu_core_with_assists(
                 &cfg,
                 &mut self.assist,
@@ -12977,15 +18707,26 @@ impl Machine {
                 &mut bus,
                 remaining,
             );
+            self.unknown_access.leave_cpu();
             std::mem::swap(&mut self.mmu, bus.inner.mmu_mut());
+            if let Some(diagnostics) = code_diagnostics {
+                diagnostics.finish_batch(&self.mem.dirty, self.cfg.ram_size_bytes);
+            }
             executed = executed.saturating_add(batch.executed);
+            self.perf.record_instructions(0, batch.executed);
 
             // Deterministically advance platform time based on executed CPU cycles.
             self.tick_platform_from_cycles(batch.executed);
+            self.observe_boot_stage_cpu(pci_config_writes_before_batch);
+            self.observe_input_consumption();
 
-            if let Some(kind) = self.reset_latch.take() {
+            if let Some(exit) = self.poll_reset_request(executed) {
+                self.flush_serial();
+                return exit;
+            }
+            if self.poll_power_off() {
                 self.flush_serial();
-                return RunExit::ResetRequested { kind, executed };
+                return RunExit::PowerOff { executed };
             }
 
             // Allow any started application processors (APs) to run a bounded amount of work per
@@ -12993,6 +18734,11 @@ impl Machine {
             // BSP delivers a SIPI.
             self.run_ap_cpus(&cfg, remaining);
 
+            if let Some(exit) = self.take_unknown_access_trap(executed) {
+                self.flush_serial();
+                return exit;
+            }
+
             match batch.exit {
                 BatchExit::Completed => {
                     // `BatchExit::Completed` means the inner Tier-0 batch hit its instruction
@@ -13009,46 +18755,7 @@ impl Machine {
                 }
                 BatchExit::Branch => continue,
                 BatchExit::Halted => {
-                    // After advancing timers, poll again so any newly-due timer interrupts are
-                    // injected into `cpu.pending.external_interrupts`.
-                    //
-                    // Only poll after the batch when we are going to re-enter execution within the
-                    // same `run_slice` call. This avoids acknowledging interrupts at the end of a
-                    // slice boundary (e.g. after an `STI` interrupt shadow expires) when the CPU
-                    // will not execute another instruction until the host calls `run_slice` again.
-                    //
-                    // Process AHCI once more here so guests that issue an AHCI command and then
-                    // execute `HLT` can still make DMA progress and be woken by INTx within the
-                    // same `run_slice` call.
-                    //
-                    // Note: `poll_platform_interrupt` synchronizes PCI INTx source levels into the
-                    // platform interrupt controller before polling, so we do not need an explicit
-                    // `sync_pci_intx_sources_to_interrupts` call here.
-                    self.process_ide();
-                    self.process_ahci();
-                    self.process_nvme();
-                    self.process_virtio_blk();
-                    self.process_aerogpu();
-                    self.process_virtio_input();
-                    // Like storage controllers, the guest may have kicked a NIC queue immediately
-                    // before executing `HLT` (e.g. E1000 TX descriptor doorbell). Poll the network
-                    // bridge again here so the device can complete DMA and raise INTx to wake the
-                    // halted CPU within the same `run_slice` call.
-                    self.poll_network();
-                    if self.poll_platform_interrupt(MAX_QUEUED_EXTERNAL_INTERRUPTS) {
-                        continue;
-                    }
-
-                    // When halted, advance platform time so timer interrupts can wake the CPU.
-                    self.idle_tick_platform_1ms();
-                    self.process_ide();
-                    self.process_ahci();
-                    self.process_nvme();
-                    self.process_virtio_blk();
-                    self.process_aerogpu();
-                    self.process_virtio_input();
-                    self.poll_network();
-                    if self.poll_platform_interrupt(MAX_QUEUED_EXTERNAL_INTERRUPTS) {
+                    if self.poll_halted_wakeup() {
                         continue;
                     }
                     self.flush_serial();
@@ -13062,12 +18769,33 @@ impl Machine {
                     return RunExit::Assist { reason, executed };
                 }
                 BatchExit::Exception(exception) => {
+                    self.perf.record_exception(&exception);
                     self.flush_serial();
                     return RunExit::Exception {
                         exception,
                         executed,
                     };
                 }
+                BatchExit::CpuExit(CpuExit::TripleFault) => {
+                    // The chipset answers the shutdown cycle with a CPU (warm) reset, which is what
+                    // lets firmware resume through the CMOS shutdown status byte.
+                    let vector_history = self
+                        .cpu
+                        .pending
+                        .exception_history()
+                        .iter()
+                        .copied()
+                        .collect();
+                    let source = ResetSource::TripleFault { vector_history };
+                    self.record_reset_event(ResetKind::Cpu, &source);
+                    self.pending_reset = Some((ResetKind::Cpu, source.clone()));
+                    self.flush_serial();
+                    return RunExit::ResetRequested {
+                        kind: ResetKind::Cpu,
+                        source,
+                        executed,
+                    };
+                }
                 BatchExit::CpuExit(exit) => {
                     self.flush_serial();
                     return RunExit::CpuExit { exit, executed };
@@ -13155,6 +18883,23 @@ impl Machine {
             .filter(|&base| base != 0)
     }
 
+    /// Guest-visible AeroGPU BAR1 (VRAM aperture) size in bytes.
+    fn aerogpu_vram_bar_size(&self) -> u64 {
+        Self::aerogpu_vram_bar_size_for_cfg(&self.cfg)
+    }
+
+    fn aerogpu_vram_bar_size_for_cfg(cfg: &MachineConfig) -> u64 {
+        cfg.aerogpu_vram_size_bytes
+            .map_or(aero_devices::pci::profile::AEROGPU_VRAM_SIZE, |size| {
+                size as u64
+            })
+    }
+
+    fn aerogpu_vram_alloc_size_for_cfg(cfg: &MachineConfig) -> usize {
+        cfg.aerogpu_vram_size_bytes
+            .unwrap_or(AEROGPU_VRAM_ALLOC_SIZE)
+    }
+
     fn legacy_vga_vram_size_bytes(&self) -> usize {
         self.legacy_vga_pci_bar_size_bytes() as usize
     }
@@ -13307,6 +19052,10 @@ impl Machine {
         let cx_before = self.cpu.state.gpr[gpr::RCX] as u16;
         let dx_before = self.cpu.state.gpr[gpr::RDX] as u16;
         let vbe_mode_before = self.bios.video.vbe.current_mode;
+        // INT 13h AH=02h (CHS read) / AH=42h (extended read): the boot loader is loading files.
+        if vector == 0x13 && matches!((ax_before >> 8) as u8, 0x02 | 0x42) {
+            self.advance_boot_stage(BootStage::Bootloader);
+        }
         #[cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
         let vbe_scanout_sig_before = vbe_mode_before.map(|mode| {
             (
@@ -13357,15 +19106,35 @@ impl Machine {
             let cdrom = cdrom
                 .as_mut()
                 .map(|iso| iso as &mut dyn firmware::bios::CdromDevice);
+            // Floppy drive accesses (INT 13h `DL < 0x80`, or INT 18h/19h when booting from a
+            // floppy) are serviced by the same image the FDC sees.
+            let floppy_access = match vector {
+                0x13 => (dx_before as u8) < 0x80,
+                0x18 | 0x19 => self.bios.config().boot_drive < 0x80,
+                _ => false,
+            };
+            let mut floppy = self.floppy.clone().filter(|_| floppy_access);
+            let disk = floppy.as_mut().unwrap_or(&mut self.disk);
             let bus: &mut dyn BiosBus = &mut self.mem;
             self.bios
-                .dispatch_interrupt(vector, &mut self.cpu.state, bus, &mut self.disk, cdrom);
+                .dispatch_interrupt(vector, &mut self.cpu.state, bus, disk, cdrom);
         }
+        self.drain_bios_post_codes();
         if force_vbe_no_clear {
             // Restore the guest-visible BX value (don't leak our forced no-clear flag).
             self.cpu.state.gpr[gpr::RBX] &= !0x8000;
         }
         let ax_after = self.cpu.state.gpr[gpr::RAX] as u16;
+        if vector == 0x13 && (self.cpu.state.rflags() & RFLAGS_CF) != 0 {
+            self.events.record_at(
+                self.guest_now_ns(),
+                machine_events::MachineEventKind::StorageError {
+                    drive: dx_before as u8,
+                    function: (ax_before >> 8) as u8,
+                    status: (ax_after >> 8) as u8,
+                },
+            );
+        }
 
         // The HLE BIOS uses its own configuration to determine the VBE LFB base and may overwrite
         // `VbeDevice::lfb_base` during INT 10h services (e.g. mode sets). Keep it coherent with the
@@ -13373,6 +19142,9 @@ impl Machine {
         // reads/writes target the correct backing store.
         if vector == 0x10 {
             self.sync_bios_vbe_lfb_base_to_display_wiring();
+            // Video services update state the display generation cannot observe through device
+            // writes (BDA cursor/page, BIOS VBE mode, host-side VRAM clears below).
+            self.display_generation.bump();
         }
 
         // If the BIOS halts the CPU during an interrupt (currently only via `bios_panic`), mirror
@@ -13466,8 +19238,8 @@ impl Machine {
                             // VGA DAC ports. For 8bpp VBE modes, mirror the updated palette into the
                             // device's DAC so rendered output matches BIOS state.
                             //
-                            // Note: The VGA model's DAC programming path accepts 6-bit components; when
-                            // the BIOS is configured for an 8-bit DAC, we downscale (>>2).
+                            // Note: The DAC stores 6-bit components; when the BIOS is configured for an
+                            // 8-bit DAC, `program_bgr0_entries` downscales (>>2).
                             let bl = (bx_before & 0x00FF) as u8;
                             if bpp == 8
                                 && ax_before == 0x4F09
@@ -13477,27 +19249,12 @@ impl Machine {
                                 let start = (dx_before as usize).min(255);
                                 let count = (cx_before as usize).min(256 - start);
                                 if count != 0 {
-                                    // Set DAC write index to `start`.
-                                    vga.port_write(0x3C8, 1, start as u32);
-
-                                    let bits = self.bios.video.vbe.dac_width_bits;
-                                    for idx in start..start + count {
-                                        let base = idx * 4;
-                                        let b = self.bios.video.vbe.palette[base];
-                                        let g = self.bios.video.vbe.palette[base + 1];
-                                        let r = self.bios.video.vbe.palette[base + 2];
-
-                                        let (r, g, b) = if bits >= 8 {
-                                            (r >> 2, g >> 2, b >> 2)
-                                        } else {
-                                            (r & 0x3F, g & 0x3F, b & 0x3F)
-                                        };
-
-                                        // VGA DAC write order is R, G, B.
-                                        vga.port_write(0x3C9, 1, r as u32);
-                                        vga.port_write(0x3C9, 1, g as u32);
-                                        vga.port_write(0x3C9, 1, b as u32);
-                                    }
+                                    vga.dac_mut().program_bgr0_entries(
+                                        start as u8,
+                                        &self.bios.video.vbe.palette
+                                            [start * 4..(start + count) * 4],
+                                        self.bios.video.vbe.dac_width_bits,
+                                    );
                                 }
                             }
 
@@ -13559,27 +19316,15 @@ impl Machine {
                                 let count = (cx_before as usize).min(256 - start);
                                 if count != 0 {
                                     let bits = self.bios.video.vbe.dac_width_bits;
-                                    let mut entries: Vec<(u8, u8, u8)> = Vec::with_capacity(count);
-                                    for idx in start..start + count {
-                                        let base = idx * 4;
-                                        let b = self.bios.video.vbe.palette[base];
-                                        let g = self.bios.video.vbe.palette[base + 1];
-                                        let r = self.bios.video.vbe.palette[base + 2];
-                                        let (r, g, b) = if bits >= 8 {
-                                            (r >> 2, g >> 2, b >> 2)
-                                        } else {
-                                            (r & 0x3F, g & 0x3F, b & 0x3F)
-                                        };
-                                        entries.push((r, g, b));
-                                    }
-
+                                    let entries = self.bios.video.vbe.palette
+                                        [start * 4..(start + count) * 4]
+                                        .to_vec();
                                     let _ = self.with_legacy_vga_frontend_mut(|vga| {
-                                        vga.port_write(0x3C8, 1, start as u32);
-                                        for (r, g, b) in entries {
-                                            vga.port_write(0x3C9, 1, r as u32);
-                                            vga.port_write(0x3C9, 1, g as u32);
-                                            vga.port_write(0x3C9, 1, b as u32);
-                                        }
+                                        vga.dac_mut().program_bgr0_entries(
+                                            start as u8,
+                                            &entries,
+                                            bits,
+                                        );
                                     });
                                 }
                             }
@@ -13642,27 +19387,11 @@ impl Machine {
 
                 let mut dev = aerogpu.borrow_mut();
                 if let Some((start, count)) = sync_range {
-                    // Set DAC write index to `start`.
-                    dev.vga_port_write_u8(0x3C8, start as u8);
-
-                    let bits = self.bios.video.vbe.dac_width_bits;
-                    for idx in start..start + count {
-                        let base = idx * 4;
-                        let b = self.bios.video.vbe.palette[base];
-                        let g = self.bios.video.vbe.palette[base + 1];
-                        let r = self.bios.video.vbe.palette[base + 2];
-
-                        let (r, g, b) = if bits >= 8 {
-                            (r >> 2, g >> 2, b >> 2)
-                        } else {
-                            (r & 0x3F, g & 0x3F, b & 0x3F)
-                        };
-
-                        // VGA DAC write order is R, G, B.
-                        dev.vga_port_write_u8(0x3C9, r);
-                        dev.vga_port_write_u8(0x3C9, g);
-                        dev.vga_port_write_u8(0x3C9, b);
-                    }
+                    dev.dac.program_bgr0_entries(
+                        start as u8,
+                        &self.bios.video.vbe.palette[start * 4..(start + count) * 4],
+                        self.bios.video.vbe.dac_width_bits,
+                    );
                 }
             }
         }
@@ -13694,7 +19423,7 @@ impl Machine {
             });
 
             if vbe_scanout_sig_before != vbe_scanout_sig_after {
-                let Some(scanout_state) = &self.scanout_state else {
+                let Some(scanout_state) = self.guest_scanout_state() else {
                     return;
                 };
                 match scanout_state.try_snapshot() {
@@ -13713,6 +19442,8 @@ impl Machine {
                             height: 0,
                             pitch_bytes: 0,
                             format: SCANOUT_FORMAT_B8G8R8X8,
+                            slot0_offset: 0,
+                            slot1_offset: 0,
                         });
                     }
                     Some((mode, lfb_base, bytes_per_scan_line, start_x, start_y)) => {
@@ -13725,6 +19456,8 @@ impl Machine {
                                 height: 0,
                                 pitch_bytes: 0,
                                 format: SCANOUT_FORMAT_B8G8R8X8,
+                                slot0_offset: 0,
+                                slot1_offset: 0,
                             };
 
                             // This legacy VBE scanout publication path currently only supports the
@@ -13756,6 +19489,8 @@ impl Machine {
                                     height: u32::from(mode_info.height),
                                     pitch_bytes: pitch as u32,
                                     format: SCANOUT_FORMAT_B8G8R8X8,
+                                    slot0_offset: 0,
+                                    slot1_offset: 0,
                                 });
                             }
                         }
@@ -13803,6 +19538,10 @@ impl Machine {
 
         PlatformInterruptController::acknowledge(&mut *interrupts, vector);
         self.cpu.pending.inject_external_interrupt(vector);
+        perf::bump(&mut self.perf.interrupt_injections);
+        if let Some(watchdog) = self.hang_watchdog.as_mut() {
+            watchdog.record_vector(vector);
+        }
         true
     }
 
@@ -13843,19 +19582,63 @@ impl Machine {
         true
     }
 
-    fn flush_serial(&mut self) {
-        let Some(uart) = &self.serial else {
-            return;
-        };
-        let mut uart = uart.borrow_mut();
-        let tx = uart.take_tx();
-        if !tx.is_empty() {
-            self.serial_log.extend_from_slice(&tx);
+    fn serial_port_enabled(cfg: &MachineConfig, port: usize) -> bool {
+        match port {
+            0 => cfg.enable_serial,
+            1 => cfg.enable_com2,
+            2 => cfg.enable_com3,
+            3 => cfg.enable_com4,
+            _ => false,
+        }
+    }
+
+    fn flush_serial(&mut self) {
+        for port in 0..SERIAL_PORT_COUNT {
+            let Some(uart) = &self.serial[port] else {
+                continue;
+            };
+            let tx = uart.borrow_mut().take_tx();
+            if tx.is_empty() {
+                continue;
+            }
+            self.serial_sinks.dispatch(port, &tx);
+            if let Some(bridge) = self.kd_bridge.as_mut().filter(|b| b.port == port) {
+                let now_ns = self
+                    .platform_clock
+                    .as_ref()
+                    .map_or(0, |clock| clock.now_ns());
+                bridge.push_from_guest(now_ns, tx);
+                continue;
+            }
+            match &self.serial_channels[port] {
+                Some(channel) => channel.push_from_guest(&tx),
+                None => serial_ports::append_to_log(
+                    &mut self.serial_logs[port],
+                    self.serial_log_capacity,
+                    &tx,
+                ),
+            }
+        }
+    }
+
+    /// Move host bytes queued on attached [`SerialChannel`]s into the UART receive FIFOs.
+    fn deliver_serial_channel_input(&mut self) {
+        for (uart, channel) in self.serial.iter().zip(&self.serial_channels) {
+            if let (Some(uart), Some(channel)) = (uart, channel) {
+                let input = channel.take_to_guest();
+                if input.is_empty() {
+                    continue;
+                }
+                let mut uart = uart.borrow_mut();
+                for byte in input {
+                    uart.push_rx(byte);
+                }
+            }
         }
     }
 
     fn mirror_bios_panic_to_serial(&mut self) {
-        let Some(uart) = &self.serial else {
+        let Some(uart) = &self.serial[0] else {
             return;
         };
         let tty = self.bios.tty_output();
@@ -13880,6 +19663,7 @@ impl Machine {
         let line = &tty[start..end];
 
         let mut uart = uart.borrow_mut();
+        let start = uart.pending_tx().len();
         for &b in b"BIOS panic: " {
             uart.write_u8(0x3F8, b);
         }
@@ -13887,6 +19671,9 @@ impl Machine {
             uart.write_u8(0x3F8, b);
         }
         uart.write_u8(0x3F8, b'\n');
+        // These bytes bypass the port bus, so record them for the event stream here.
+        self.events
+            .record_output(Some(0), &uart.pending_tx()[start..]);
     }
 }
 
@@ -13898,6 +19685,11 @@ struct MachineUsbSnapshot {
     ehci_ns_remainder: u64,
     xhci: Option<Vec<u8>>,
     xhci_ns_remainder: u64,
+    /// `(handle, physical port)` for each host-proxied USB device. In-flight transfers are not
+    /// recorded.
+    usb_proxies: Vec<(u32, u8)>,
+    /// xHCI root ports populated through [`Machine::xhci_attach_device`], ascending.
+    xhci_attached_ports: Vec<u8>,
 }
 
 impl MachineUsbSnapshot {
@@ -13907,11 +19699,13 @@ impl MachineUsbSnapshot {
     const TAG_EHCI_STATE: u16 = 4;
     const TAG_XHCI_NS_REMAINDER: u16 = 5;
     const TAG_XHCI_STATE: u16 = 6;
+    const TAG_USB_PROXIES: u16 = 7;
+    const TAG_XHCI_ATTACHED_PORTS: u16 = 8;
 }
 
 impl IoSnapshot for MachineUsbSnapshot {
     const DEVICE_ID: [u8; 4] = *b"USBC";
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 2);
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 4);
 
     fn save_state(&self) -> Vec<u8> {
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
@@ -13927,6 +19721,17 @@ impl IoSnapshot for MachineUsbSnapshot {
             w.field_u64(Self::TAG_XHCI_NS_REMAINDER, self.xhci_ns_remainder);
             w.field_bytes(Self::TAG_XHCI_STATE, xhci.clone());
         }
+        if !self.usb_proxies.is_empty() {
+            let mut enc = Encoder::new().u32(self.usb_proxies.len() as u32);
+            for &(id, port) in &self.usb_proxies {
+                enc = enc.u32(id).u8(port);
+            }
+            w.field_bytes(Self::TAG_USB_PROXIES, enc.finish());
+        }
+        if !self.xhci_attached_ports.is_empty() {
+            let enc = Encoder::new().vec_u8(&self.xhci_attached_ports);
+            w.field_bytes(Self::TAG_XHCI_ATTACHED_PORTS, enc.finish());
+        }
         w.finish()
     }
 
@@ -13942,6 +19747,25 @@ impl IoSnapshot for MachineUsbSnapshot {
 
         self.xhci_ns_remainder = r.u64(Self::TAG_XHCI_NS_REMAINDER)?.unwrap_or(0);
         self.xhci = r.bytes(Self::TAG_XHCI_STATE).map(|buf| buf.to_vec());
+
+        self.usb_proxies.clear();
+        if let Some(buf) = r.bytes(Self::TAG_USB_PROXIES) {
+            let mut d = Decoder::new(buf);
+            let count = d.u32()?;
+            for _ in 0..count {
+                let id = d.u32()?;
+                let port = d.u8()?;
+                self.usb_proxies.push((id, port));
+            }
+            d.finish()?;
+        }
+
+        self.xhci_attached_ports.clear();
+        if let Some(buf) = r.bytes(Self::TAG_XHCI_ATTACHED_PORTS) {
+            let mut d = Decoder::new(buf);
+            self.xhci_attached_ports = d.vec_u8()?;
+            d.finish()?;
+        }
         Ok(())
     }
 }
@@ -14057,6 +19881,7 @@ impl snapshot::SnapshotSource for Machine {
 
     fn device_states(&self) -> Vec<snapshot::DeviceState> {
         const V1: u16 = 1;
+        self.sync_skipped_device_ticks();
         let mut devices = Vec::new();
         const MSIX_MESSAGE_CONTROL_OFFSET: u16 = 0x02;
         // MSI-X capability Message Control bits we mirror from canonical PCI config space into
@@ -14085,8 +19910,9 @@ impl snapshot::SnapshotSource for Machine {
             data: {
                 // Legacy snapshots stored only A20 enabled state.
                 // Newer snapshots append the platform clock (ns) so time-based device models
-                // (RTC/HPET/AeroGPU vblank scheduling) can be restored deterministically.
-                let mut data = Vec::with_capacity(1 + 8);
+                // (RTC/HPET/AeroGPU vblank scheduling) can be restored deterministically, followed
+                // by the display generation counter.
+                let mut data = Vec::with_capacity(1 + 8 + 8);
                 data.push(self.chipset.a20().enabled() as u8);
                 let now_ns = self
                     .platform_clock
@@ -14094,16 +19920,28 @@ impl snapshot::SnapshotSource for Machine {
                     .map(aero_interrupts::clock::Clock::now_ns)
                     .unwrap_or(0);
                 data.extend_from_slice(&now_ns.to_le_bytes());
+                data.extend_from_slice(&self.display_generation.get().to_le_bytes());
                 data
             },
         });
 
-        // Accumulated serial output (drained from the UART by `Machine::run_slice`).
+        // Accumulated serial output (drained from the UARTs by `Machine::run_slice`).
+        //
+        // Version 1 stored the COM1 log verbatim; version 2 stores a `u32` length-prefixed log for
+        // each of COM1..COM4.
         devices.push(snapshot::DeviceState {
             id: snapshot::DeviceId::SERIAL,
-            version: V1,
+            version: 2,
             flags: 0,
-            data: self.serial_log.clone(),
+            data: {
+                let mut data = Vec::new();
+                for log in &self.serial_logs {
+                    let len = u32::try_from(log.len()).unwrap_or(u32::MAX);
+                    data.extend_from_slice(&len.to_le_bytes());
+                    data.extend_from_slice(&log[..len as usize]);
+                }
+                data
+            },
         });
 
         // VGA/VBE (registers + full VRAM).
@@ -14119,7 +19957,11 @@ impl snapshot::SnapshotSource for Machine {
                 id: snapshot::DeviceId::AEROGPU,
                 version: AEROGPU_SNAPSHOT_VERSION_V2,
                 flags: 0,
-                data: encode_aerogpu_snapshot_v2(&aerogpu.borrow(), &aerogpu_mmio.borrow()),
+                data: encode_aerogpu_snapshot_v2(
+                    &aerogpu.borrow(),
+                    &aerogpu_mmio.borrow(),
+                    usize::try_from(self.aerogpu_vram_bar_size()).unwrap_or(usize::MAX),
+                ),
             });
         }
 
@@ -14389,6 +20231,135 @@ impl snapshot::SnapshotSource for Machine {
                 &*virtio_input_tablet.borrow(),
             ));
         }
+        if let Some(virtio_balloon) = &self.virtio_balloon {
+            let bdf = aero_devices::pci::profile::VIRTIO_BALLOON.bdf;
+            if let Some(pci_cfg) = &self.pci_cfg {
+                let (command, bar0_base, msix_ctrl_bits) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let mut command = 0;
+                    let mut bar0_base = None;
+                    let mut msix_ctrl_bits = None;
+                    if let Some(cfg) = pci_cfg.bus_mut().device_config_mut(bdf) {
+                        command = cfg.command();
+                        bar0_base = cfg.bar_range(0).map(|range| range.base);
+                        if let Some(msix_off) = cfg.find_capability(PCI_CAP_ID_MSIX) {
+                            let ctrl = cfg
+                                .read(u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET, 2)
+                                as u16;
+                            msix_ctrl_bits = Some(ctrl & MSIX_MESSAGE_CONTROL_MIRROR_MASK);
+                        }
+                    }
+                    (command, bar0_base, msix_ctrl_bits)
+                };
+
+                let mut dev = virtio_balloon.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+
+                if let Some(msix_ctrl_bits) = msix_ctrl_bits {
+                    if let Some(msix_off) = dev.config_mut().find_capability(PCI_CAP_ID_MSIX) {
+                        let ctrl_off = u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET;
+                        let runtime_ctrl = dev.config_mut().read(ctrl_off, 2) as u16;
+                        let new_ctrl =
+                            (runtime_ctrl & !MSIX_MESSAGE_CONTROL_MIRROR_MASK) | msix_ctrl_bits;
+                        dev.config_mut().write(ctrl_off, 2, u32::from(new_ctrl));
+                    }
+                }
+            }
+
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::VIRTIO_BALLOON,
+                &*virtio_balloon.borrow(),
+            ));
+        }
+        if let Some(virtio_9p) = &self.virtio_9p {
+            let bdf = aero_devices::pci::profile::VIRTIO_9P.bdf;
+            if let Some(pci_cfg) = &self.pci_cfg {
+                let (command, bar0_base, msix_ctrl_bits) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let mut command = 0;
+                    let mut bar0_base = None;
+                    let mut msix_ctrl_bits = None;
+                    if let Some(cfg) = pci_cfg.bus_mut().device_config_mut(bdf) {
+                        command = cfg.command();
+                        bar0_base = cfg.bar_range(0).map(|range| range.base);
+                        if let Some(msix_off) = cfg.find_capability(PCI_CAP_ID_MSIX) {
+                            let ctrl = cfg
+                                .read(u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET, 2)
+                                as u16;
+                            msix_ctrl_bits = Some(ctrl & MSIX_MESSAGE_CONTROL_MIRROR_MASK);
+                        }
+                    }
+                    (command, bar0_base, msix_ctrl_bits)
+                };
+
+                let mut dev = virtio_9p.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+
+                if let Some(msix_ctrl_bits) = msix_ctrl_bits {
+                    if let Some(msix_off) = dev.config_mut().find_capability(PCI_CAP_ID_MSIX) {
+                        let ctrl_off = u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET;
+                        let runtime_ctrl = dev.config_mut().read(ctrl_off, 2) as u16;
+                        let new_ctrl =
+                            (runtime_ctrl & !MSIX_MESSAGE_CONTROL_MIRROR_MASK) | msix_ctrl_bits;
+                        dev.config_mut().write(ctrl_off, 2, u32::from(new_ctrl));
+                    }
+                }
+            }
+
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::VIRTIO_9P,
+                &*virtio_9p.borrow(),
+            ));
+        }
+        if let Some(virtio_rng) = &self.virtio_rng {
+            let bdf = aero_devices::pci::profile::VIRTIO_RNG.bdf;
+            if let Some(pci_cfg) = &self.pci_cfg {
+                let (command, bar0_base, msix_ctrl_bits) = {
+                    let mut pci_cfg = pci_cfg.borrow_mut();
+                    let mut command = 0;
+                    let mut bar0_base = None;
+                    let mut msix_ctrl_bits = None;
+                    if let Some(cfg) = pci_cfg.bus_mut().device_config_mut(bdf) {
+                        command = cfg.command();
+                        bar0_base = cfg.bar_range(0).map(|range| range.base);
+                        if let Some(msix_off) = cfg.find_capability(PCI_CAP_ID_MSIX) {
+                            let ctrl = cfg
+                                .read(u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET, 2)
+                                as u16;
+                            msix_ctrl_bits = Some(ctrl & MSIX_MESSAGE_CONTROL_MIRROR_MASK);
+                        }
+                    }
+                    (command, bar0_base, msix_ctrl_bits)
+                };
+
+                let mut dev = virtio_rng.borrow_mut();
+                dev.set_pci_command(command);
+                if let Some(bar0_base) = bar0_base {
+                    dev.config_mut().set_bar_base(0, bar0_base);
+                }
+
+                if let Some(msix_ctrl_bits) = msix_ctrl_bits {
+                    if let Some(msix_off) = dev.config_mut().find_capability(PCI_CAP_ID_MSIX) {
+                        let ctrl_off = u16::from(msix_off) + MSIX_MESSAGE_CONTROL_OFFSET;
+                        let runtime_ctrl = dev.config_mut().read(ctrl_off, 2) as u16;
+                        let new_ctrl =
+                            (runtime_ctrl & !MSIX_MESSAGE_CONTROL_MIRROR_MASK) | msix_ctrl_bits;
+                        dev.config_mut().write(ctrl_off, 2, u32::from(new_ctrl));
+                    }
+                }
+            }
+
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::VIRTIO_RNG,
+                &*virtio_rng.borrow(),
+            ));
+        }
         if self.uhci.is_some() || self.ehci.is_some() || self.xhci.is_some() {
             let mut wrapper = MachineUsbSnapshot::default();
 
@@ -14492,6 +20463,13 @@ impl snapshot::SnapshotSource for Machine {
                 wrapper.xhci_ns_remainder = self.xhci_ns_remainder;
             }
 
+            wrapper.usb_proxies = self
+                .usb_proxy_handles()
+                .into_iter()
+                .map(|(handle, port)| (handle.0, port))
+                .collect();
+            wrapper.xhci_attached_ports = self.xhci_attached_ports.iter().copied().collect();
+
             devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
                 snapshot::DeviceId::USB,
                 &wrapper,
@@ -14646,6 +20624,26 @@ impl snapshot::SnapshotSource for Machine {
                 &*hpet.borrow(),
             ));
         }
+        if let Some(dma) = &self.dma {
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::DMA,
+                &*dma.borrow(),
+            ));
+        }
+        if let Some(tpm) = &self.tpm {
+            // CRB registers + buffer; backend state is embedded via `TpmBackend::save_state`.
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::TPM,
+                &*tpm.borrow(),
+            ));
+        }
+        if let Some(fdc) = &self.fdc {
+            // Controller/drive state only; the floppy image is referenced via `disk_id=3`.
+            devices.push(snapshot::io_snapshot_bridge::device_state_from_io_snapshot(
+                snapshot::DeviceId::FDC,
+                &*fdc.borrow(),
+            ));
+        }
 
         if let Some(ctrl) = &self.i8042 {
             let ctrl = ctrl.borrow();
@@ -14690,6 +20688,9 @@ impl snapshot::SnapshotSource for Machine {
         if let Some(disk) = self.ide_primary_master_overlay.clone() {
             disks.push(disk);
         }
+        if let Some(disk) = self.floppy_overlay.clone() {
+            disks.push(disk);
+        }
         snapshot::DiskOverlayRefs { disks }
     }
 
@@ -14698,7 +20699,6 @@ impl snapshot::SnapshotSource for Machine {
     }
 
     fn read_ram(&self, offset: u64, buf: &mut [u8]) -> snapshot::Result<()> {
-        let low_ram_end = firmware::bios::PCIE_ECAM_BASE;
         let end = offset
             .checked_add(buf.len() as u64)
             .ok_or(snapshot::SnapshotError::Corrupt("ram offset overflow"))?;
@@ -14708,25 +20708,18 @@ impl snapshot::SnapshotSource for Machine {
         // Snapshots encode RAM as a dense byte array of length `ram_size_bytes` (not including any
         // guest-physical MMIO holes). When RAM is remapped above 4GiB to make room for the PCIe
         // ECAM/PCI hole, translate dense RAM offsets into the corresponding guest-physical
-        // addresses.
+        // addresses using the same layout published by `Machine::guest_ram_layout`.
+        let layout = self.guest_ram_layout();
         let ram = self.mem.bus.ram();
 
         let mut cur_offset = offset;
         let mut remaining = buf;
         while !remaining.is_empty() {
-            let phys = if cur_offset < low_ram_end {
-                cur_offset
-            } else {
-                FOUR_GIB + (cur_offset - low_ram_end)
-            };
-
-            let chunk_len = if cur_offset < low_ram_end {
-                let until_boundary = low_ram_end - cur_offset;
-                let until_boundary = usize::try_from(until_boundary).unwrap_or(remaining.len());
-                remaining.len().min(until_boundary)
-            } else {
-                remaining.len()
-            };
+            let (phys, contiguous) = guest_ram::backing_offset_to_gpa(&layout, cur_offset)
+                .ok_or(snapshot::SnapshotError::Corrupt("ram read out of range"))?;
+            let chunk_len = remaining
+                .len()
+                .min(usize::try_from(contiguous).unwrap_or(usize::MAX));
 
             ram.read_into(phys, &mut remaining[..chunk_len]).map_err(
                 |_err: GuestMemoryError| snapshot::SnapshotError::Corrupt("ram read failed"),
@@ -14922,7 +20915,7 @@ impl snapshot::SnapshotTarget for Machine {
         // configuration intends to expose, even if the snapshot did not include a BIOS section (or
         // it failed to decode).
         if self.cfg.enable_aerogpu {
-            let blocks = aero_devices::pci::profile::AEROGPU_VRAM_SIZE.div_ceil(64 * 1024);
+            let blocks = self.aerogpu_vram_bar_size().div_ceil(64 * 1024);
             self.bios.video.vbe.total_memory_64kb_blocks = blocks.min(u64::from(u16::MAX)) as u16;
         } else if use_legacy_vga {
             let vram_bytes = self
@@ -14951,16 +20944,41 @@ impl snapshot::SnapshotTarget for Machine {
                         clock.set_ns(u64::from_le_bytes(buf));
                     }
                 }
+
+                // The display generation follows the clock. Older snapshots leave the counter
+                // untouched.
+                if let Some(bytes) = state.data.get(9..17) {
+                    let mut buf = [0u8; 8];
+                    buf.copy_from_slice(bytes);
+                    self.display_generation.set(u64::from_le_bytes(buf));
+                }
             }
         }
+        // Restored device state never matches the cached framebuffer.
+        self.display_presented_generation = None;
 
         // Accumulated serial output.
         if let Some(state) = by_id.remove(&snapshot::DeviceId::SERIAL) {
-            if state.version == 1 {
-                if let Some(uart) = &self.serial {
-                    let _ = uart.borrow_mut().take_tx();
+            for uart in self.serial.iter().flatten() {
+                let _ = uart.borrow_mut().take_tx();
+            }
+            match state.version {
+                1 => self.serial_logs[0] = state.data,
+                2 => {
+                    let mut rest = state.data.as_slice();
+                    for log in &mut self.serial_logs {
+                        let Some((len, tail)) = rest.split_first_chunk::<4>() else {
+                            break;
+                        };
+                        let len = (u32::from_le_bytes(*len) as usize).min(tail.len());
+                        *log = tail[..len].to_vec();
+                        rest = &tail[len..];
+                    }
                 }
-                self.serial_log = state.data;
+                _ => {}
+            }
+            for log in &mut self.serial_logs {
+                serial_ports::truncate_log(log, self.serial_log_capacity);
             }
         }
 
@@ -14988,32 +21006,58 @@ impl snapshot::SnapshotTarget for Machine {
 
                         // Port mappings are part of machine wiring, not the snapshot payload, so
                         // install the default VGA port ranges now.
-                        self.io.register_shared_range(
+                        self.io.register_shared_range_with_policy(
                             aero_gpu_vga::VGA_LEGACY_IO_START,
                             aero_gpu_vga::VGA_LEGACY_IO_LEN,
+                            aero_platform::io::AccessPolicy::Split(
+                                aero_platform::io::AccessSizes::BYTE,
+                            ),
                             {
                                 let vga = vga.clone();
-                                move |_port| Box::new(VgaPortIoDevice { dev: vga.clone() })
+                                let generation = self.display_generation.clone();
+                                move |_port| {
+                                    Box::new(DisplayWriteTracker {
+                                        inner: VgaPortIoDevice { dev: vga.clone() },
+                                        generation: generation.clone(),
+                                    })
+                                }
                             },
                         );
-                        self.io.register_shared_range(
+                        self.io.register_shared_range_with_policy(
                             aero_gpu_vga::VBE_DISPI_IO_START,
                             aero_gpu_vga::VBE_DISPI_IO_LEN,
+                            aero_platform::io::AccessPolicy::Natural(
+                                aero_platform::io::AccessSizes::WORD,
+                            ),
                             {
                                 let vga = vga.clone();
-                                move |_port| Box::new(VgaPortIoDevice { dev: vga.clone() })
+                                let generation = self.display_generation.clone();
+                                move |_port| {
+                                    Box::new(DisplayWriteTracker {
+                                        inner: VgaPortIoDevice { dev: vga.clone() },
+                                        generation: generation.clone(),
+                                    })
+                                }
                             },
                         );
 
                         // MMIO mappings persist in the physical bus; install legacy + LFB.
                         let legacy_base = aero_gpu_vga::VGA_LEGACY_MEM_START as u64;
                         let legacy_len = aero_gpu_vga::VGA_LEGACY_MEM_LEN as u64;
-                        self.mem.map_mmio_once(legacy_base, legacy_len, {
+                        self.mem.map_mmio_once("vga", legacy_base, legacy_len, {
                             let vga = vga.clone();
+                            let generation = self.display_generation.clone();
+                            let counter = self.mmio_perf.vga_legacy.clone();
                             move || {
-                                Box::new(VgaLegacyMmioHandler {
-                                    base_paddr: aero_gpu_vga::VGA_LEGACY_MEM_START,
-                                    dev: vga,
+                                Box::new(perf::CountedMmio {
+                                    inner: DisplayWriteTracker {
+                                        inner: VgaLegacyMmioHandler {
+                                            base_paddr: aero_gpu_vga::VGA_LEGACY_MEM_START,
+                                            dev: vga,
+                                        },
+                                        generation,
+                                    },
+                                    counter,
                                 })
                             }
                         });
@@ -15021,9 +21065,15 @@ impl snapshot::SnapshotTarget for Machine {
                             let vga = vga.borrow();
                             (u64::from(vga.lfb_base()), vga.vram_size() as u64)
                         };
-                        self.mem.map_mmio_once(lfb_base, lfb_len, {
+                        self.mem.map_mmio_once("vga-lfb", lfb_base, lfb_len, {
                             let vga = vga.clone();
-                            move || Box::new(VgaLfbMmioHandler { dev: vga })
+                            let generation = self.display_generation.clone();
+                            move || {
+                                Box::new(DisplayWriteTracker {
+                                    inner: VgaLfbMmioHandler { dev: vga },
+                                    generation,
+                                })
+                            }
                         });
 
                         vga
@@ -15051,6 +21101,8 @@ impl snapshot::SnapshotTarget for Machine {
         }
 
         if let Some(state) = by_id.remove(&snapshot::DeviceId::AEROGPU) {
+            // VRAM contents are replaced wholesale; treat any outstanding export as stale.
+            self.aerogpu_vram_generation += 1;
             // When AeroGPU is disabled, ignore any AeroGPU snapshot payloads (config mismatch),
             // similar to the VGA restore behaviour above.
             if !self.cfg.enable_aerogpu {
@@ -15068,32 +21120,19 @@ impl snapshot::SnapshotTarget for Machine {
                             dev.vram[..copy_len].copy_from_slice(&decoded.vram[..copy_len]);
 
                             if let Some(dac) = &decoded.vga_dac {
-                                dev.pel_mask = dac.pel_mask;
-                                dev.dac_palette = dac.palette;
+                                dev.dac.set_pel_mask(dac.pel_mask);
+                                for (idx, rgb) in dac.palette.iter().enumerate() {
+                                    dev.dac.set_entry(idx as u8, *rgb);
+                                }
                             } else {
                                 // Restore the VGA DAC palette from the BIOS VBE palette state. The BIOS
                                 // snapshot captures VBE palette entries (B,G,R,0), and AeroGPU-backed
                                 // 8bpp VBE rendering uses the emulated DAC palette.
-                                let bits = self.bios.video.vbe.dac_width_bits;
-                                let pal = &self.bios.video.vbe.palette;
-                                dev.vga_port_write_u8(0x3C8, 0); // set DAC write index
-                                for idx in 0..256usize {
-                                    let base = idx * 4;
-                                    let b = pal[base];
-                                    let g = pal[base + 1];
-                                    let r = pal[base + 2];
-
-                                    let (r, g, b) = if bits >= 8 {
-                                        (r >> 2, g >> 2, b >> 2)
-                                    } else {
-                                        (r & 0x3F, g & 0x3F, b & 0x3F)
-                                    };
-
-                                    // VGA DAC write order is R, G, B.
-                                    dev.vga_port_write_u8(0x3C9, r);
-                                    dev.vga_port_write_u8(0x3C9, g);
-                                    dev.vga_port_write_u8(0x3C9, b);
-                                }
+                                dev.dac.program_bgr0_entries(
+                                    0,
+                                    &self.bios.video.vbe.palette,
+                                    self.bios.video.vbe.dac_width_bits,
+                                );
                             }
                         }
 
@@ -15116,26 +21155,11 @@ impl snapshot::SnapshotTarget for Machine {
                     // Keep the AeroGPU-emulated DAC palette coherent with the BIOS VBE palette so
                     // 8bpp VBE output is deterministic even when restoring older snapshots.
                     if !restored_dac {
-                        let bits = self.bios.video.vbe.dac_width_bits;
-                        let pal = &self.bios.video.vbe.palette;
-                        vram.vga_port_write_u8(0x3C8, 0); // set DAC write index
-                        for idx in 0..256usize {
-                            let base = idx * 4;
-                            let b = pal[base];
-                            let g = pal[base + 1];
-                            let r = pal[base + 2];
-
-                            let (r, g, b) = if bits >= 8 {
-                                (r >> 2, g >> 2, b >> 2)
-                            } else {
-                                (r & 0x3F, g & 0x3F, b & 0x3F)
-                            };
-
-                            // VGA DAC write order is R, G, B.
-                            vram.vga_port_write_u8(0x3C9, r);
-                            vram.vga_port_write_u8(0x3C9, g);
-                            vram.vga_port_write_u8(0x3C9, b);
-                        }
+                        vram.dac.program_bgr0_entries(
+                            0,
+                            &self.bios.video.vbe.palette,
+                            self.bios.video.vbe.dac_width_bits,
+                        );
                     }
                 }
             }
@@ -15353,6 +21377,30 @@ impl snapshot::SnapshotTarget for Machine {
             }
         }
 
+        // The INTx router snapshot is authoritative for PIRQ routing. Mirror it into the PIIX3
+        // PIRQ route registers so snapshots taken before those registers were modelled (which
+        // restore them as zero, i.e. "IRQ0") don't disconnect every PIRQ on the next poll.
+        if restored_pci_intx {
+            if let (Some(pci_intx), Some(pci_cfg)) = (&self.pci_intx, &self.pci_cfg) {
+                let pci_intx = pci_intx.borrow();
+                let mut pci_cfg = pci_cfg.borrow_mut();
+                if let Some(cfg) = pci_cfg
+                    .bus_mut()
+                    .device_config_mut(aero_devices::pci::profile::ISA_PIIX3.bdf)
+                {
+                    for pirq in 0..4 {
+                        let value =
+                            aero_devices::pci::piix3_pirq_route_from_gsi(pci_intx.pirq_route(pirq));
+                        cfg.write(
+                            aero_devices::pci::PIIX3_PIRQ_ROUTE_OFFSET + pirq as u16,
+                            1,
+                            u32::from(value),
+                        );
+                    }
+                }
+            }
+        }
+
         // 4) Restore storage controllers (AHCI + NVMe + IDE + virtio-blk). These must be restored after the interrupt
         // controller + PCI core so any restored interrupt state can be re-driven deterministically.
         for state in disk_controller_states {
@@ -15463,6 +21511,20 @@ impl snapshot::SnapshotTarget for Machine {
             let _ =
                 snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *acpi_pm);
         }
+        if let (Some(dma), Some(state)) = (&self.dma, by_id.remove(&snapshot::DeviceId::DMA)) {
+            let mut dma = dma.borrow_mut();
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *dma);
+        }
+        // The FDC keeps its attached floppy image across `load_state()`.
+        if let (Some(fdc), Some(state)) = (&self.fdc, by_id.remove(&snapshot::DeviceId::FDC)) {
+            let mut fdc = fdc.borrow_mut();
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *fdc);
+        }
+
+        if let (Some(tpm), Some(state)) = (&self.tpm, by_id.remove(&snapshot::DeviceId::TPM)) {
+            let mut tpm = tpm.borrow_mut();
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *tpm);
+        }
 
         // 6) Restore HPET.
         let mut restored_hpet = false;
@@ -15605,6 +21667,54 @@ impl snapshot::SnapshotTarget for Machine {
             virtio.rewind_queue_next_avail_to_next_used(0);
         }
 
+        // Restore virtio-balloon. The device holds the guest's stats buffer without producing a
+        // used entry; that is runtime-only state, so clear it and rewind the stats queue so the
+        // transport re-pops the buffer post-restore. Inflated pages need no extra work: they were
+        // saved as zero pages, and sparse RAM backends skip committing all-zero chunks on restore.
+        if let (Some(virtio), Some(state)) = (
+            &self.virtio_balloon,
+            by_id.remove(&snapshot::DeviceId::VIRTIO_BALLOON),
+        ) {
+            let mut virtio = virtio.borrow_mut();
+            if let Some(balloon) = virtio.device_mut::<VirtioBalloon>() {
+                aero_virtio::devices::VirtioDevice::reset(balloon);
+            }
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *virtio);
+            virtio.rewind_queue_next_avail_to_next_used(
+                aero_virtio::devices::balloon::VIRTIO_BALLOON_QUEUE_STATS,
+            );
+        }
+
+        // Restore virtio-9p. Requests complete synchronously, so no chain is in flight; the fid
+        // table comes back with the device state and keeps referring to the host backend's paths.
+        if let (Some(virtio), Some(state)) = (
+            &self.virtio_9p,
+            by_id.remove(&snapshot::DeviceId::VIRTIO_9P),
+        ) {
+            let mut virtio = virtio.borrow_mut();
+            if let Some(share) = virtio.device_mut::<Virtio9p>() {
+                aero_virtio::devices::VirtioDevice::reset(share);
+            }
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *virtio);
+        }
+
+        // Restore virtio-rng. Held request buffers are runtime-only, so clear them and rewind the
+        // request queue so the transport re-pops them post-restore; the deterministic stream
+        // resumes at the saved position, and an installed host source stays in place.
+        if let (Some(virtio), Some(state)) = (
+            &self.virtio_rng,
+            by_id.remove(&snapshot::DeviceId::VIRTIO_RNG),
+        ) {
+            let mut virtio = virtio.borrow_mut();
+            if let Some(rng) = virtio.device_mut::<VirtioRng>() {
+                aero_virtio::devices::VirtioDevice::reset(rng);
+            }
+            let _ = snapshot::io_snapshot_bridge::apply_io_snapshot_to_device(&state, &mut *virtio);
+            virtio.rewind_queue_next_avail_to_next_used(
+                aero_virtio::devices::rng::VIRTIO_RNG_QUEUE_REQUEST,
+            );
+        }
+
         // Backward compatibility: older snapshots stored both virtio-input PCI functions under the
         // single wrapper id `DeviceId::VIRTIO_INPUT` (inner snapshot 4CC `VINP`).
         if !restored_virtio_input_pair {
@@ -15642,9 +21752,9 @@ impl snapshot::SnapshotTarget for Machine {
         // can be re-driven deterministically.
         if let Some(state) = by_id.remove(&snapshot::DeviceId::USB) {
             // If synthetic USB HID devices are enabled, pre-attach the canonical external hub +
-            // synthetic devices *before* loading the UHCI snapshot so `RootHub::load_snapshot_ports`
-            // will reuse the existing device instances (handle stability).
-            self.ensure_uhci_synthetic_usb_hid_topology();
+            // synthetic devices *before* loading the UHCI/xHCI snapshot so the port loaders reuse
+            // the existing device instances (handle stability).
+            self.ensure_synthetic_usb_hid_topology();
 
             const NS_PER_MS: u64 = 1_000_000;
             let data = state.data;
@@ -15660,11 +21770,17 @@ impl snapshot::SnapshotTarget for Machine {
             self.uhci_ns_remainder = 0;
             self.ehci_ns_remainder = 0;
             self.xhci_ns_remainder = 0;
+            // Proxied USB devices are re-discovered from the restored topology below; in-flight
+            // host transfers never survive a restore.
+            let mut usb_proxy_records = Vec::new();
+            let mut xhci_attached_port_records = Vec::new();
             let inner_id = data.get(8..12).and_then(|id| <[u8; 4]>::try_from(id).ok());
             if inner_id == Some(*b"USBC") {
                 let mut wrapper = MachineUsbSnapshot::default();
                 if wrapper.load_state(&data).is_ok() {
                     saw_xhci_state_in_snapshot = wrapper.xhci.is_some();
+                    usb_proxy_records = core::mem::take(&mut wrapper.usb_proxies);
+                    xhci_attached_port_records = core::mem::take(&mut wrapper.xhci_attached_ports);
 
                     if let Some(uhci_state) = wrapper.uhci.as_deref() {
                         if let Some(uhci) = &self.uhci {
@@ -15809,6 +21925,17 @@ impl snapshot::SnapshotTarget for Machine {
                     }
                 }
             }
+
+            self.xhci_attached_ports = xhci_attached_port_records.into_iter().collect();
+
+            self.usb_proxies.clear();
+            for (id, port) in usb_proxy_records {
+                if let Some(device) = self.usb_physical_port_proxy_device(port) {
+                    self.usb_proxies
+                        .insert(id, MachineUsbProxy { port, device });
+                    self.next_usb_proxy_id = self.next_usb_proxy_id.max(id.wrapping_add(1).max(1));
+                }
+            }
         }
 
         // If the machine has xHCI enabled but the snapshot did not include any xHCI payload, reset
@@ -15875,6 +22002,11 @@ impl snapshot::SnapshotTarget for Machine {
             .iter()
             .find(|d| d.disk_id == Self::DISK_ID_IDE_PRIMARY_MASTER)
             .cloned();
+        let floppy_overlay = overlays
+            .disks
+            .iter()
+            .find(|d| d.disk_id == Self::DISK_ID_FLOPPY)
+            .cloned();
 
         // Record the restored refs for the host/coordinator so it can re-open and re-attach the
         // appropriate storage backends after restore.
@@ -15885,6 +22017,7 @@ impl snapshot::SnapshotTarget for Machine {
         self.ahci_port0_overlay = ahci_port0_overlay;
         self.ide_secondary_master_atapi_overlay = ide_secondary_master_atapi_overlay;
         self.ide_primary_master_overlay = ide_primary_master_overlay;
+        self.floppy_overlay = floppy_overlay;
     }
 
     fn ram_len(&self) -> usize {
@@ -15892,31 +22025,23 @@ impl snapshot::SnapshotTarget for Machine {
     }
 
     fn write_ram(&mut self, offset: u64, data: &[u8]) -> snapshot::Result<()> {
-        let low_ram_end = firmware::bios::PCIE_ECAM_BASE;
         let end = offset
             .checked_add(data.len() as u64)
             .ok_or(snapshot::SnapshotError::Corrupt("ram offset overflow"))?;
         if end > self.cfg.ram_size_bytes {
             return Err(snapshot::SnapshotError::Corrupt("ram write out of range"));
         }
+        let layout = self.guest_ram_layout();
         let ram = self.mem.bus.ram_mut();
 
         let mut cur_offset = offset;
         let mut remaining = data;
         while !remaining.is_empty() {
-            let phys = if cur_offset < low_ram_end {
-                cur_offset
-            } else {
-                FOUR_GIB + (cur_offset - low_ram_end)
-            };
-
-            let chunk_len = if cur_offset < low_ram_end {
-                let until_boundary = low_ram_end - cur_offset;
-                let until_boundary = usize::try_from(until_boundary).unwrap_or(remaining.len());
-                remaining.len().min(until_boundary)
-            } else {
-                remaining.len()
-            };
+            let (phys, contiguous) = guest_ram::backing_offset_to_gpa(&layout, cur_offset)
+                .ok_or(snapshot::SnapshotError::Corrupt("ram write out of range"))?;
+            let chunk_len = remaining
+                .len()
+                .min(usize::try_from(contiguous).unwrap_or(usize::MAX));
 
             ram.write_from(phys, &remaining[..chunk_len])
                 .map_err(|_err: GuestMemoryError| {
@@ -15934,11 +22059,16 @@ impl snapshot::SnapshotTarget for Machine {
         // restoring, even if the caller bypasses the `Machine::restore_snapshot_*` helper methods
         // and drives snapshot restore directly via `aero_snapshot::restore_snapshot`.
         self.detach_network();
+        // Device idle state and HPET deadlines were learned from the pre-restore guest.
+        self.tick_scheduler.wake_all();
         // `inject_ps2_mouse_buttons` maintains a host-side "previous buttons" cache to synthesize
         // per-button transitions from an absolute mask. Snapshot restore rewinds guest time and
         // restores guest device state; invalidate the cache so the next injection call can re-sync
         // correctly even if the guest mouse state differs from the cached host value.
         self.ps2_mouse_buttons = 0xFF;
+        // Pending pointer frames were meant for the pre-restore guest.
+        self.virtio_mouse_backlog.clear();
+        self.ps2_mouse_backlog.clear();
         // `consumer_usage_backend` tracks which input backend (virtio vs synthetic USB
         // consumer-control) delivered the press for each consumer usage. Snapshot restore can be
         // applied into a new machine instance that lacks this host-side pairing state, so drop any
@@ -15952,8 +22082,18 @@ impl snapshot::SnapshotTarget for Machine {
         self.input_batch_keyboard_backend = 0;
         self.input_batch_mouse_buttons_mask = 0;
         self.input_batch_mouse_backend = 0;
-        self.reset_latch.clear();
-        self.assist = AssistContext::default();
+        self.clear_reset_request();
+        self.reset_assist_context();
+        if let Some(watchdog) = self.hang_watchdog.as_mut() {
+            watchdog.rearm();
+        }
+        if let Some(sampler) = self.dirty_sampler.as_mut() {
+            sampler.rearm();
+        }
+        if let Some(tracker) = self.input_latency.as_mut() {
+            tracker.abandon_pending();
+        }
+        self.event_injector.clear();
         self.display_fb.clear();
         self.display_width = 0;
         self.display_height = 0;
@@ -16071,12 +22211,28 @@ mod tests {
         let expected_file = file!();
         let expected_line = line!() + 2;
         let (file, line) = capture_panic_location(|| {
-            mem.map_mmio_once(u64::MAX, 2, || Box::new(DummyMmio));
+            mem.map_mmio_once("test", u64::MAX, 2, || Box::new(DummyMmio));
         });
         assert_eq!(file, expected_file);
         assert_eq!(line, expected_line);
     }
 
+    #[test]
+    fn virtio_dma_is_not_a20_masked() {
+        let chipset = ChipsetState::new(false);
+        let mut mem = SystemMemory::new(2 * 1024 * 1024, chipset.a20()).expect("construct");
+
+        let mut dma = VirtioDmaMemory::new(&mut mem);
+        dma.write(0x10_0000, &[0xA5]).unwrap();
+        let mut byte = [0u8; 1];
+        dma.read(0x0, &mut byte).unwrap();
+        assert_eq!(byte, [0]);
+
+        // The CPU-visible view still wraps 0x10_0000 onto 0x0.
+        assert_eq!(memory::MemoryBus::read_u8(&mut mem, 0x10_0000), 0);
+        assert_eq!(mem.bus.ram().read_u8_le(0x10_0000).unwrap(), 0xA5);
+    }
+
     #[test]
     fn aerogpu_legacy_vga_mmio_byte_iteration_does_not_wrap_u64_offsets() {
         // Regression test: `legacy_vga_read`/`legacy_vga_write` iterate bytewise for 1/2/4/8-byte
@@ -16183,7 +22339,7 @@ mod tests {
         let expected_uhci = vec![0x12, 0x34, 0x56];
         let expected_remainder = 42u64;
 
-        // `USBC` v1.0 only contained UHCI fields. Ensure the v1.2 decoder continues accepting it
+        // `USBC` v1.0 only contained UHCI fields. Ensure the current decoder continues accepting it
         // and defaults EHCI/xHCI to empty/none.
         let mut w = SnapshotWriter::new(*b"USBC", SnapshotVersion::new(1, 0));
         w.field_u64(
@@ -16211,7 +22367,7 @@ mod tests {
         let expected_ehci = vec![0x99, 0x88];
         let expected_ehci_remainder = 7u64;
 
-        // `USBC` v1.1 added optional EHCI fields. Ensure the v1.2 decoder continues accepting it
+        // `USBC` v1.1 added optional EHCI fields. Ensure the current decoder continues accepting it
         // and defaults xHCI to empty/none.
         let mut w = SnapshotWriter::new(*b"USBC", SnapshotVersion::new(1, 1));
         w.field_u64(
@@ -16238,7 +22394,7 @@ mod tests {
     }
 
     #[test]
-    fn usb_snapshot_container_roundtrips_uhci_xhci_and_ehci_state() {
+    fn usb_snapshot_container_roundtrips_uhci_xhci_ehci_and_proxy_state() {
         let snapshot = MachineUsbSnapshot {
             uhci: Some(vec![1, 2, 3]),
             uhci_ns_remainder: 500_123,
@@ -16246,12 +22402,14 @@ mod tests {
             ehci_ns_remainder: 250_456,
             xhci: Some(vec![0xaa, 0xbb]),
             xhci_ns_remainder: 750_789,
+            usb_proxies: vec![(1, 0), (3, 1)],
+            xhci_attached_ports: vec![0, 9],
         };
 
         let bytes = snapshot.save_state();
 
         let mut decoded = MachineUsbSnapshot::default();
-        decoded.load_state(&bytes).expect("load USBC v1.2");
+        decoded.load_state(&bytes).expect("load USBC v1.4");
 
         assert_eq!(decoded, snapshot);
 
@@ -16268,12 +22426,14 @@ mod tests {
             ehci_ns_remainder: 250_456,
             xhci: None,
             xhci_ns_remainder: 0,
+            usb_proxies: Vec::new(),
+            xhci_attached_ports: Vec::new(),
         };
 
         let bytes = snapshot.save_state();
 
         let mut decoded = MachineUsbSnapshot::default();
-        decoded.load_state(&bytes).expect("load USBC v1.2");
+        decoded.load_state(&bytes).expect("load USBC v1.4");
 
         assert_eq!(decoded, snapshot);
 
@@ -16292,10 +22452,12 @@ mod tests {
             // Even if the field is non-zero, USBC should omit xHCI fields when no xHCI snapshot is
             // present.
             xhci_ns_remainder: 123_456,
+            usb_proxies: Vec::new(),
+            xhci_attached_ports: Vec::new(),
         };
 
         let bytes = snapshot.save_state();
-        let r = IoSnapshotReader::parse(&bytes, *b"USBC").expect("parse USBC v1.2");
+        let r = IoSnapshotReader::parse(&bytes, *b"USBC").expect("parse USBC v1.4");
         assert!(
             r.u64(MachineUsbSnapshot::TAG_XHCI_NS_REMAINDER)
                 .expect("read xHCI remainder tag")
@@ -17091,6 +23253,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn timer_coherence_holds_across_guest_cpu_frequencies() {
+        for tsc_hz in [1_000_000, 1_193_182 * 3, 2_400_000_000, 3_000_000_001] {
+            let mut m = Machine::new(MachineConfig {
+                ram_size_bytes: 16 * 1024 * 1024,
+                cpu_count: 2,
+                enable_pc_platform: true,
+                enable_acpi: true,
+                enable_serial: false,
+                enable_i8042: false,
+                enable_e1000: false,
+                enable_virtio_net: false,
+                ..Default::default()
+            })
+            .unwrap();
+            m.cpu.time.set_tsc_hz(tsc_hz);
+            m.cpu.state.msr.tsc = m.cpu.time.read_tsc();
+            m.write_physical_u64(hpet::HPET_MMIO_BASE + 0x10, 1);
+
+            let report = m.verify_timer_coherence(250_000_000);
+
+            assert!(report.sample(TimerSource::Hpet).is_some());
+            assert!(report.sample(TimerSource::Tsc(1)).is_some());
+            let outliers: Vec<_> = report.outliers().collect();
+            assert!(outliers.is_empty(), "tsc_hz={tsc_hz}: {outliers:#?}");
+        }
+    }
+
     #[test]
     fn halted_run_slice_advances_bios_bda_ticks_without_pc_platform() {
         use firmware::bios::{BDA_TICK_COUNT_ADDR, TICKS_PER_DAY};
@@ -19526,13 +25716,7 @@ mod tests {
             attr_index: 0,
             attr_regs: AeroGpuDevice::default_attr_regs(),
             attr_flip_flop: false,
-            pel_mask: 0xFF,
-            dac_write_index: 0,
-            dac_write_subindex: 0,
-            dac_write_latch: [0; 3],
-            dac_read_index: 0,
-            dac_read_subindex: 0,
-            dac_palette: AeroGpuDevice::default_dac_palette(),
+            dac: VgaDac::new(),
         }
     }
 
@@ -19594,10 +25778,10 @@ mod tests {
         assert_eq!(regs.cursor_fb_gpa_pending_lo, 0);
         assert!(!regs.cursor_fb_gpa_lo_pending);
 
-        assert_eq!(vram.pel_mask, pel_mask);
-        assert_eq!(vram.dac_palette[0], [0, 0, 0]);
-        assert_eq!(vram.dac_palette[1], [1, 2, 3]);
-        assert_eq!(vram.dac_palette[255], [4, 5, 6]);
+        assert_eq!(vram.dac.pel_mask(), pel_mask);
+        assert_eq!(vram.dac.palette()[0], [0, 0, 0]);
+        assert_eq!(vram.dac.palette()[1], [1, 2, 3]);
+        assert_eq!(vram.dac.palette()[255], [4, 5, 6]);
     }
 
     #[test]
@@ -19670,8 +25854,8 @@ mod tests {
         assert_eq!(regs.cursor_fb_gpa_pending_lo, cursor_pending_lo);
         assert!(!regs.cursor_fb_gpa_lo_pending);
 
-        assert_eq!(vram.pel_mask, pel_mask);
-        assert_eq!(vram.dac_palette[0], [7, 8, 9]);
+        assert_eq!(vram.dac.pel_mask(), pel_mask);
+        assert_eq!(vram.dac.palette()[0], [7, 8, 9]);
     }
 
     #[test]
@@ -19771,11 +25955,8 @@ mod tests {
         // `next_vblank_ns`). If `now_ns` begins with bytes that match one of the 4-byte tags (for
         // example `b"DACI"`), the v2 decoder must not attempt to parse it as another section.
         let mut src_vram = new_minimal_aerogpu_device_for_snapshot_tests();
-        src_vram.dac_read_index = 0x12;
-        src_vram.dac_read_subindex = 0x34;
-        src_vram.dac_write_index = 0x56;
-        src_vram.dac_write_subindex = 0x78;
-        src_vram.dac_write_latch = [9, 10, 11];
+        src_vram.dac.restore_indices(0x12, 1, 0x56, 2, [9, 10, 11]);
+        src_vram.dac.set_entry(3, [0x3F, 0x20, 0x01]);
         src_vram.attr_index = 0x1F;
         src_vram.attr_flip_flop = true;
 
@@ -19783,7 +25964,8 @@ mod tests {
         let now_ns = u64::from_le_bytes(*b"DACI\0\0\0\0");
         src_bar0.tick_vblank(now_ns);
 
-        let bytes = encode_aerogpu_snapshot_v2(&src_vram, &src_bar0);
+        let bytes =
+            encode_aerogpu_snapshot_v2(&src_vram, &src_bar0, aero_gpu_vga::DEFAULT_VRAM_SIZE);
 
         let mut dst_vram = new_minimal_aerogpu_device_for_snapshot_tests();
         let mut dst_bar0 = AeroGpuMmioDevice::default();
@@ -19792,11 +25974,7 @@ mod tests {
 
         let regs = dst_bar0.snapshot_v1();
         assert_eq!(regs.now_ns, now_ns);
-        assert_eq!(dst_vram.dac_read_index, src_vram.dac_read_index);
-        assert_eq!(dst_vram.dac_read_subindex, src_vram.dac_read_subindex);
-        assert_eq!(dst_vram.dac_write_index, src_vram.dac_write_index);
-        assert_eq!(dst_vram.dac_write_subindex, src_vram.dac_write_subindex);
-        assert_eq!(dst_vram.dac_write_latch, src_vram.dac_write_latch);
+        assert_eq!(dst_vram.dac, src_vram.dac);
         assert_eq!(dst_vram.attr_index, src_vram.attr_index);
         assert_eq!(dst_vram.attr_flip_flop, src_vram.attr_flip_flop);
     }
@@ -19853,7 +26031,8 @@ mod tests {
         src_vram.vbe_dispi_y_offset = 20;
 
         let src_bar0 = AeroGpuMmioDevice::default();
-        let bytes = encode_aerogpu_snapshot_v2(&src_vram, &src_bar0);
+        let bytes =
+            encode_aerogpu_snapshot_v2(&src_vram, &src_bar0, aero_gpu_vga::DEFAULT_VRAM_SIZE);
 
         let mut dst_vram = new_minimal_aerogpu_device_for_snapshot_tests();
         let mut dst_bar0 = AeroGpuMmioDevice::default();
@@ -19940,12 +26119,22 @@ mod tests {
             interrupts,
             ApCpus::All(m.ap_cpus.as_mut_slice()),
             &mut m.mem,
+            None,
+        );
+        let inner = aero_cpu_core::PagingBus::new_with_io(
+            phys,
+            StrictIoPortBus {
+                io: &mut m.io,
+                counts: &mut m.perf.port_io,
+            },
         );
-        let inner = aero_cpu_core::PagingBus::new_with_io(phys, StrictIoPortBus { io: &mut m.io });
         let mut bus = MachineCpuBus {
             a20: m.chipset.a20(),
             reset: m.reset_latch.clone(),
             inner,
+            bench: None,
+            unknown_access: &m.unknown_access,
+            code_diagnostics: None,
         };
 
         assert!(!bus.supports_bulk_copy());
@@ -20133,7 +26322,7 @@ mod tests {
             .borrow_mut()
             .set_backend(Box::new(backend));
 
-        m.display_present();
+        m.display_present(true);
 
         assert_eq!(m.display_resolution(), (width, height));
         assert_eq!(m.display_framebuffer()[0], u32::from_le_bytes(rgba_backend));
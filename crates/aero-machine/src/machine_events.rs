@@ -0,0 +1,453 @@
+//! Unified, timestamped machine event stream (see [`crate::Machine::take_events`]).
+//!
+//! Serial output, DebugCon output, boot-stage transitions, reset requests, AeroGPU errors, BIOS
+//! disk failures and unclaimed guest accesses all feed one bounded ring, so a host can rebuild a
+//! single timeline instead of interleaving separately polled logs. The existing per-subsystem
+//! surfaces (serial logs, DebugCon buffer, unknown-access log, ...) keep working unchanged; the
+//! stream is recorded in addition to them.
+//!
+//! Every event carries a sequence number (global recording order) and the guest time at which it
+//! was recorded. Byte output is captured at the port write that produced it, so serial and
+//! DebugCon writes issued by the same CPU batch keep their relative order. Guest time only
+//! advances between batches, so events recorded inside one batch share a timestamp; order them by
+//! `seq`.
+//!
+//! Only categories enabled by [`crate::Machine::set_event_mask`] are recorded (none by default).
+//! When the ring is full, new events are dropped rather than evicting undrained ones, and the
+//! drop is counted against the event's category (see [`crate::Machine::take_event_drops`]).
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ops::{BitOr, BitOrAssign};
+use std::rc::Rc;
+
+use aero_devices::debugcon::{SharedDebugConLog, DEBUGCON_PORT};
+use aero_devices::serial::{Serial16550Port, SharedSerial16550};
+use aero_platform::io::{IoPortBus, PortIoDevice};
+use aero_platform::reset::ResetKind;
+
+use crate::{BootStage, ResetSource, UnknownAccessSpace};
+
+/// Default number of events the ring holds (see [`crate::Machine::set_event_capacity`]).
+pub const MACHINE_EVENT_DEFAULT_CAPACITY: usize = 4096;
+
+/// Largest byte payload of a single [`MachineEventKind::SerialOut`] / [`MachineEventKind::DebugCon`]
+/// event. Consecutive output from the same source at the same guest time is coalesced into the
+/// newest event up to this size.
+pub const MACHINE_EVENT_MAX_CHUNK: usize = 256;
+
+/// Event category, used for filtering and drop accounting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MachineEventCategory {
+    SerialOut,
+    DebugCon,
+    BootStage,
+    ResetRequested,
+    AeroGpuError,
+    StorageError,
+    UnknownIo,
+}
+
+impl MachineEventCategory {
+    pub const ALL: [Self; 7] = [
+        Self::SerialOut,
+        Self::DebugCon,
+        Self::BootStage,
+        Self::ResetRequested,
+        Self::AeroGpuError,
+        Self::StorageError,
+        Self::UnknownIo,
+    ];
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The mask that selects only this category.
+    pub const fn mask(self) -> MachineEventMask {
+        MachineEventMask(1 << self.index())
+    }
+}
+
+/// Set of [`MachineEventCategory`] values to record (see [`crate::Machine::set_event_mask`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MachineEventMask(u32);
+
+impl MachineEventMask {
+    pub const NONE: Self = Self(0);
+    pub const SERIAL_OUT: Self = MachineEventCategory::SerialOut.mask();
+    pub const DEBUGCON: Self = MachineEventCategory::DebugCon.mask();
+    pub const BOOT_STAGE: Self = MachineEventCategory::BootStage.mask();
+    pub const RESET_REQUESTED: Self = MachineEventCategory::ResetRequested.mask();
+    pub const AEROGPU_ERROR: Self = MachineEventCategory::AeroGpuError.mask();
+    pub const STORAGE_ERROR: Self = MachineEventCategory::StorageError.mask();
+    pub const UNKNOWN_IO: Self = MachineEventCategory::UnknownIo.mask();
+    pub const ALL: Self = Self((1 << MachineEventCategory::ALL.len()) - 1);
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Build a mask from raw bits, ignoring bits that name no category.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub const fn contains(self, category: MachineEventCategory) -> bool {
+        self.0 & category.mask().0 != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for MachineEventMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for MachineEventMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Payload of a [`MachineEvent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineEventKind {
+    /// Bytes the guest transmitted on COM port `port` (`0..SERIAL_PORT_COUNT`).
+    SerialOut { port: u8, bytes: Vec<u8> },
+    /// Bytes the guest wrote to the DebugCon port (`0xE9`).
+    DebugCon { bytes: Vec<u8> },
+    /// The machine reached a new [`BootStage`].
+    BootStage(BootStage),
+    /// The guest requested a reset (reported once per request, when it is latched).
+    ResetRequested {
+        kind: ResetKind,
+        source: ResetSource,
+    },
+    /// AeroGPU latched an `AEROGPU_ERROR_*` code in its BAR0 error registers.
+    AeroGpuError { code: u32, fence: u64 },
+    /// A BIOS INT 13h disk service returned with CF set.
+    StorageError { drive: u8, function: u8, status: u8 },
+    /// A vCPU touched a port or physical address nothing decodes (not deduplicated; ports in
+    /// [`crate::UnknownAccessConfig`]'s ignore lists are skipped).
+    UnknownIo {
+        space: UnknownAccessSpace,
+        addr: u64,
+        size: u32,
+        write: bool,
+    },
+}
+
+impl MachineEventKind {
+    pub fn category(&self) -> MachineEventCategory {
+        match self {
+            Self::SerialOut { .. } => MachineEventCategory::SerialOut,
+            Self::DebugCon { .. } => MachineEventCategory::DebugCon,
+            Self::BootStage(_) => MachineEventCategory::BootStage,
+            Self::ResetRequested { .. } => MachineEventCategory::ResetRequested,
+            Self::AeroGpuError { .. } => MachineEventCategory::AeroGpuError,
+            Self::StorageError { .. } => MachineEventCategory::StorageError,
+            Self::UnknownIo { .. } => MachineEventCategory::UnknownIo,
+        }
+    }
+}
+
+/// One entry of the machine event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineEvent {
+    /// Recording order across all categories. Keeps counting across reset and snapshot restore.
+    pub seq: u64,
+    /// Guest time of the recording in nanoseconds since reset (same time base as
+    /// [`crate::BootStageTransition::guest_time_ns`]).
+    pub guest_time_ns: u64,
+    pub kind: MachineEventKind,
+}
+
+/// Events dropped because the ring was full, per category (see
+/// [`crate::Machine::take_event_drops`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MachineEventDrops {
+    counts: [u64; MachineEventCategory::ALL.len()],
+}
+
+impl MachineEventDrops {
+    pub fn get(&self, category: MachineEventCategory) -> u64 {
+        self.counts[category.index()]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts
+            .iter()
+            .fold(0u64, |acc, n| acc.saturating_add(*n))
+    }
+}
+
+/// Shared between the machine and the device taps that record into it.
+#[derive(Debug)]
+pub(crate) struct MachineEventLog {
+    mask: Cell<MachineEventMask>,
+    capacity: Cell<usize>,
+    // Guest time stamped on events recorded from device callbacks; refreshed by the machine before
+    // each CPU batch.
+    now_ns: Cell<u64>,
+    next_seq: Cell<u64>,
+    ring: RefCell<VecDeque<MachineEvent>>,
+    drops: Cell<MachineEventDrops>,
+}
+
+impl Default for MachineEventLog {
+    fn default() -> Self {
+        Self {
+            mask: Cell::new(MachineEventMask::NONE),
+            capacity: Cell::new(MACHINE_EVENT_DEFAULT_CAPACITY),
+            now_ns: Cell::new(0),
+            next_seq: Cell::new(0),
+            ring: RefCell::new(VecDeque::new()),
+            drops: Cell::new(MachineEventDrops::default()),
+        }
+    }
+}
+
+impl MachineEventLog {
+    pub(crate) fn mask(&self) -> MachineEventMask {
+        self.mask.get()
+    }
+
+    pub(crate) fn set_mask(&self, mask: MachineEventMask) {
+        self.mask.set(mask);
+    }
+
+    #[inline]
+    pub(crate) fn wants(&self, category: MachineEventCategory) -> bool {
+        self.mask.get().contains(category)
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity.get()
+    }
+
+    /// Bound the ring to `capacity` events (at least one). Undrained events past the new bound
+    /// are discarded newest first and counted as drops.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let capacity = capacity.max(1);
+        self.capacity.set(capacity);
+        let mut ring = self.ring.borrow_mut();
+        while ring.len() > capacity {
+            if let Some(event) = ring.pop_back() {
+                self.count_drop(event.kind.category());
+            }
+        }
+    }
+
+    pub(crate) fn now_ns(&self) -> u64 {
+        self.now_ns.get()
+    }
+
+    pub(crate) fn set_now_ns(&self, now_ns: u64) {
+        self.now_ns.set(now_ns);
+    }
+
+    /// Record `kind` at the current guest time if its category is enabled.
+    pub(crate) fn record(&self, kind: MachineEventKind) {
+        self.record_at(self.now_ns.get(), kind);
+    }
+
+    /// Record `kind` at `guest_time_ns` if its category is enabled.
+    pub(crate) fn record_at(&self, guest_time_ns: u64, kind: MachineEventKind) {
+        let category = kind.category();
+        if !self.wants(category) {
+            return;
+        }
+        let mut ring = self.ring.borrow_mut();
+        if ring.len() >= self.capacity.get() {
+            self.count_drop(category);
+            return;
+        }
+        let seq = self.next_seq.get();
+        self.next_seq.set(seq.wrapping_add(1));
+        ring.push_back(MachineEvent {
+            seq,
+            guest_time_ns,
+            kind,
+        });
+    }
+
+    /// Record guest output bytes, appending to the newest event when it is output from the same
+    /// source at the same guest time.
+    pub(crate) fn record_output(&self, serial_port: Option<u8>, mut bytes: &[u8]) {
+        let category = match serial_port {
+            Some(_) => MachineEventCategory::SerialOut,
+            None => MachineEventCategory::DebugCon,
+        };
+        if bytes.is_empty() || !self.wants(category) {
+            return;
+        }
+        let now_ns = self.now_ns.get();
+        {
+            let mut ring = self.ring.borrow_mut();
+            if let Some(last) = ring.back_mut().filter(|last| last.guest_time_ns == now_ns) {
+                let tail = match (&mut last.kind, serial_port) {
+                    (MachineEventKind::SerialOut { port, bytes }, Some(p)) if *port == p => {
+                        Some(bytes)
+                    }
+                    (MachineEventKind::DebugCon { bytes }, None) => Some(bytes),
+                    _ => None,
+                };
+                if let Some(tail) = tail {
+                    let n = MACHINE_EVENT_MAX_CHUNK
+                        .saturating_sub(tail.len())
+                        .min(bytes.len());
+                    tail.extend_from_slice(&bytes[..n]);
+                    bytes = &bytes[n..];
+                }
+            }
+        }
+        for chunk in bytes.chunks(MACHINE_EVENT_MAX_CHUNK) {
+            let bytes = chunk.to_vec();
+            self.record_at(
+                now_ns,
+                match serial_port {
+                    Some(port) => MachineEventKind::SerialOut { port, bytes },
+                    None => MachineEventKind::DebugCon { bytes },
+                },
+            );
+        }
+    }
+
+    /// Drain up to `max` events, oldest first.
+    pub(crate) fn take(&self, max: usize) -> Vec<MachineEvent> {
+        let mut ring = self.ring.borrow_mut();
+        let n = max.min(ring.len());
+        ring.drain(..n).collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ring.borrow().len()
+    }
+
+    pub(crate) fn take_drops(&self) -> MachineEventDrops {
+        self.drops.take()
+    }
+
+    fn count_drop(&self, category: MachineEventCategory) {
+        let mut drops = self.drops.get();
+        let count = &mut drops.counts[category.index()];
+        *count = count.saturating_add(1);
+        self.drops.set(drops);
+    }
+}
+
+/// Where a [`OutputTap`] finds the bytes produced by the wrapped device.
+enum OutputSource {
+    Serial { port: u8, uart: SharedSerial16550 },
+    DebugCon(SharedDebugConLog),
+}
+
+impl OutputSource {
+    fn len(&self) -> usize {
+        match self {
+            Self::Serial { uart, .. } => uart.borrow().pending_tx().len(),
+            Self::DebugCon(log) => log.borrow().len(),
+        }
+    }
+
+    fn record_since(&self, start: usize, events: &MachineEventLog) {
+        match self {
+            Self::Serial { port, uart } => {
+                let uart = uart.borrow();
+                let tx = uart.pending_tx();
+                events.record_output(Some(*port), tx.get(start..).unwrap_or_default());
+            }
+            Self::DebugCon(log) => {
+                let log = log.borrow();
+                events.record_output(None, log.get(start..).unwrap_or_default());
+            }
+        }
+    }
+}
+
+/// Port wrapper that records the output bytes a write to the wrapped device produces.
+struct OutputTap {
+    inner: Box<dyn PortIoDevice>,
+    source: OutputSource,
+    events: Rc<MachineEventLog>,
+}
+
+impl OutputTap {
+    fn category(&self) -> MachineEventCategory {
+        match self.source {
+            OutputSource::Serial { .. } => MachineEventCategory::SerialOut,
+            OutputSource::DebugCon(_) => MachineEventCategory::DebugCon,
+        }
+    }
+}
+
+impl PortIoDevice for OutputTap {
+    fn read(&mut self, port: u16, size: u8) -> u32 {
+        self.inner.read(port, size)
+    }
+
+    fn write(&mut self, port: u16, size: u8, value: u32) {
+        if !self.events.wants(self.category()) {
+            self.inner.write(port, size, value);
+            return;
+        }
+        let start = self.source.len();
+        self.inner.write(port, size, value);
+        self.source.record_since(start, &self.events);
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn debug_name(&self) -> &'static str {
+        self.inner.debug_name()
+    }
+}
+
+/// Wrap the transmit register port of an already registered UART so its output is recorded.
+pub(crate) fn install_serial_tap(
+    io: &mut IoPortBus,
+    port: u8,
+    base: u16,
+    uart: SharedSerial16550,
+    events: Rc<MachineEventLog>,
+) {
+    let inner = io
+        .unregister(base)
+        .unwrap_or_else(|| Box::new(Serial16550Port::new(uart.clone(), base)));
+    io.register(
+        base,
+        Box::new(OutputTap {
+            inner,
+            source: OutputSource::Serial { port, uart },
+            events,
+        }),
+    );
+}
+
+/// Wrap the already registered DebugCon port so its output is recorded.
+pub(crate) fn install_debugcon_tap(
+    io: &mut IoPortBus,
+    log: SharedDebugConLog,
+    events: Rc<MachineEventLog>,
+) {
+    let Some(inner) = io.unregister(DEBUGCON_PORT) else {
+        return;
+    };
+    io.register(
+        DEBUGCON_PORT,
+        Box::new(OutputTap {
+            inner,
+            source: OutputSource::DebugCon(log),
+            events,
+        }),
+    );
+}
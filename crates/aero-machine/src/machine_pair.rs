@@ -0,0 +1,233 @@
+//! Two machines whose NICs are cabled back-to-back, for deterministic guest-to-guest network tests.
+//!
+//! [`MachinePair`] owns two [`Machine`]s and installs a [`LoopbackEndpoint`] as each one's network
+//! backend. Frames transmitted by one guest are held on the link until the test calls
+//! [`MachinePair::pump_network`], which moves them to the peer's receive queue and polls both NICs.
+//! Nothing crosses the link on its own, so the interleaving of the two guests' traffic is entirely
+//! decided by the order of `run_*` and `pump_network` calls.
+//!
+//! Both directions are bounded; frames that do not fit are dropped and counted in
+//! [`LoopbackDirectionStats`].
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use aero_net_backend::NetworkBackend;
+
+use crate::{Machine, MachineConfig, MachineError, RunExit};
+
+/// Default per-direction frame capacity of a [`MachinePair`] link.
+pub const DEFAULT_LOOPBACK_QUEUE_FRAMES: usize = 256;
+
+/// One side of a [`MachinePair`], as passed to pair methods that address a single machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSide {
+    A,
+    B,
+}
+
+impl PairSide {
+    fn index(self) -> usize {
+        match self {
+            PairSide::A => 0,
+            PairSide::B => 1,
+        }
+    }
+
+    fn peer(self) -> Self {
+        match self {
+            PairSide::A => PairSide::B,
+            PairSide::B => PairSide::A,
+        }
+    }
+}
+
+/// Counters for one direction of a [`MachinePair`] link (frames *transmitted by* that side).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LoopbackDirectionStats {
+    /// Frames accepted from the transmitting NIC onto the link.
+    pub transmitted: u64,
+    /// Frames handed to the receiving NIC.
+    pub delivered: u64,
+    /// Frames dropped at transmit time because the link already held `capacity` frames.
+    pub dropped_tx_full: u64,
+    /// Frames dropped while pumping because the peer had not drained `capacity` earlier frames.
+    pub dropped_rx_full: u64,
+}
+
+#[derive(Debug, Default)]
+struct LoopbackDirection {
+    /// Transmitted, not yet pumped across the link.
+    in_flight: VecDeque<Vec<u8>>,
+    /// Pumped across, waiting for the receiving NIC to poll them.
+    deliverable: VecDeque<Vec<u8>>,
+    stats: LoopbackDirectionStats,
+}
+
+#[derive(Debug)]
+struct LoopbackLink {
+    capacity: usize,
+    /// Indexed by the transmitting side.
+    directions: [LoopbackDirection; 2],
+}
+
+impl LoopbackLink {
+    /// Move every in-flight frame to the peer's receive queue; returns how many were moved.
+    fn transfer(&mut self) -> usize {
+        let capacity = self.capacity;
+        let mut moved = 0;
+        for dir in &mut self.directions {
+            while let Some(frame) = dir.in_flight.pop_front() {
+                if dir.deliverable.len() >= capacity {
+                    dir.stats.dropped_rx_full += 1;
+                    continue;
+                }
+                dir.deliverable.push_back(frame);
+                moved += 1;
+            }
+        }
+        moved
+    }
+}
+
+/// [`NetworkBackend`] for one side of a [`MachinePair`] link.
+pub struct LoopbackEndpoint {
+    link: Rc<RefCell<LoopbackLink>>,
+    side: PairSide,
+}
+
+impl NetworkBackend for LoopbackEndpoint {
+    fn transmit(&mut self, frame: Vec<u8>) {
+        let mut link = self.link.borrow_mut();
+        let capacity = link.capacity;
+        let dir = &mut link.directions[self.side.index()];
+        if dir.in_flight.len() >= capacity {
+            dir.stats.dropped_tx_full += 1;
+            return;
+        }
+        dir.in_flight.push_back(frame);
+        dir.stats.transmitted += 1;
+    }
+
+    fn poll_receive(&mut self) -> Option<Vec<u8>> {
+        let mut link = self.link.borrow_mut();
+        let dir = &mut link.directions[self.side.peer().index()];
+        let frame = dir.deliverable.pop_front()?;
+        dir.stats.delivered += 1;
+        Some(frame)
+    }
+}
+
+/// Two [`Machine`]s with back-to-back NICs and a test-controlled delivery cadence.
+///
+/// Both configurations should enable a NIC (E1000 or virtio-net) with distinct MAC addresses. The
+/// loopback backends are host-side state like any other network backend: they are not part of
+/// snapshots, so re-install them with [`MachinePair::reconnect`] after restoring either machine.
+pub struct MachinePair {
+    machines: [Machine; 2],
+    link: Rc<RefCell<LoopbackLink>>,
+}
+
+impl MachinePair {
+    /// Create both machines and connect them with a [`DEFAULT_LOOPBACK_QUEUE_FRAMES`]-deep link.
+    pub fn new(cfg_a: MachineConfig, cfg_b: MachineConfig) -> Result<Self, MachineError> {
+        Self::with_queue_capacity(cfg_a, cfg_b, DEFAULT_LOOPBACK_QUEUE_FRAMES)
+    }
+
+    /// Like [`MachinePair::new`], with an explicit per-direction frame capacity.
+    ///
+    /// The capacity bounds both the frames waiting for [`MachinePair::pump_network`] and the frames
+    /// pumped across but not yet polled by the receiving NIC.
+    pub fn with_queue_capacity(
+        cfg_a: MachineConfig,
+        cfg_b: MachineConfig,
+        capacity: usize,
+    ) -> Result<Self, MachineError> {
+        let machines = [Machine::new(cfg_a)?, Machine::new(cfg_b)?];
+        let link = Rc::new(RefCell::new(LoopbackLink {
+            capacity,
+            directions: Default::default(),
+        }));
+        let mut pair = Self { machines, link };
+        pair.reconnect();
+        Ok(pair)
+    }
+
+    /// (Re)install the loopback backends on both machines, e.g. after a snapshot restore.
+    ///
+    /// Frames already on the link are kept.
+    pub fn reconnect(&mut self) {
+        for side in [PairSide::A, PairSide::B] {
+            let endpoint = LoopbackEndpoint {
+                link: self.link.clone(),
+                side,
+            };
+            self.machines[side.index()].set_network_backend(Box::new(endpoint));
+        }
+    }
+
+    /// Borrow one of the machines.
+    pub fn machine(&self, side: PairSide) -> &Machine {
+        &self.machines[side.index()]
+    }
+
+    /// Mutably borrow one of the machines.
+    pub fn machine_mut(&mut self, side: PairSide) -> &mut Machine {
+        &mut self.machines[side.index()]
+    }
+
+    /// Shorthand for `machine_mut(PairSide::A)`.
+    pub fn a(&mut self) -> &mut Machine {
+        self.machine_mut(PairSide::A)
+    }
+
+    /// Shorthand for `machine_mut(PairSide::B)`.
+    pub fn b(&mut self) -> &mut Machine {
+        self.machine_mut(PairSide::B)
+    }
+
+    /// Run machine A for up to `insts_a` instructions, then machine B for up to `insts_b`.
+    ///
+    /// No frames cross the link; call [`MachinePair::pump_network`] to deliver them.
+    pub fn run_both(&mut self, insts_a: u64, insts_b: u64) -> (RunExit, RunExit) {
+        let exit_a = self.machines[0].run_slice(insts_a);
+        let exit_b = self.machines[1].run_slice(insts_b);
+        (exit_a, exit_b)
+    }
+
+    /// Deliver every frame currently on the link, in both directions.
+    ///
+    /// Polls both NICs (A then B) to collect frames the guests have queued for transmit, moves all
+    /// in-flight frames to the peer's receive queue, then polls both NICs again so the frames reach
+    /// guest RX buffers. Frames transmitted during the second poll (e.g. replies generated by the
+    /// device model itself) stay in flight until the next call. Returns the number of frames moved.
+    pub fn pump_network(&mut self) -> usize {
+        for m in &mut self.machines {
+            m.poll_network();
+        }
+        let moved = self.link.borrow_mut().transfer();
+        for m in &mut self.machines {
+            m.poll_network();
+        }
+        moved
+    }
+
+    /// Link counters for frames transmitted by `side`.
+    pub fn link_stats(&self, side: PairSide) -> LoopbackDirectionStats {
+        self.link.borrow().directions[side.index()].stats
+    }
+
+    /// Frames transmitted by `side` that are waiting for [`MachinePair::pump_network`].
+    pub fn frames_in_flight(&self, side: PairSide) -> usize {
+        self.link.borrow().directions[side.index()].in_flight.len()
+    }
+
+    /// Split into the two machines, dropping the link (and any frames still on it).
+    pub fn into_machines(self) -> (Machine, Machine) {
+        let [mut a, mut b] = self.machines;
+        a.detach_network();
+        b.detach_network();
+        (a, b)
+    }
+}
@@ -0,0 +1,45 @@
+//! Host-persisted non-volatile machine state (see [`crate::Machine::export_nvram`]).
+//!
+//! The blob is an `aero-io-snapshot` TLV record so NVRAM-like stores added later become new
+//! field tags; readers skip tags they do not know. Only guest-owned bytes round-trip meaningfully:
+//! firmware-owned fields are re-derived from the machine configuration when the blob is applied.
+
+use aero_devices::rtc_cmos::CMOS_NVRAM_LEN;
+use aero_io_snapshot::io::state::{
+    SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
+};
+
+const NVRAM_ID: [u8; 4] = *b"NVRM";
+const NVRAM_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+
+const TAG_RTC_CMOS: u16 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct NvramImage {
+    /// RTC CMOS RAM bank (`0x10..=0x7F`).
+    pub(crate) rtc_cmos: Option<[u8; CMOS_NVRAM_LEN]>,
+}
+
+impl NvramImage {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut w = SnapshotWriter::new(NVRAM_ID, NVRAM_VERSION);
+        if let Some(bank) = &self.rtc_cmos {
+            w.field_bytes(TAG_RTC_CMOS, bank.to_vec());
+        }
+        w.finish()
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> SnapshotResult<Self> {
+        let r = SnapshotReader::parse(bytes, NVRAM_ID)?;
+        r.ensure_device_major(NVRAM_VERSION.major)?;
+
+        let rtc_cmos = r
+            .bytes(TAG_RTC_CMOS)
+            .map(|buf| {
+                buf.try_into()
+                    .map_err(|_| SnapshotError::InvalidFieldEncoding("rtc cmos nvram"))
+            })
+            .transpose()?;
+        Ok(Self { rtc_cmos })
+    }
+}
@@ -0,0 +1,161 @@
+//! Guest-visible "Aero" paravirtual discovery (see [`crate::Machine::paravirt_info`]).
+//!
+//! Every machine reports the hypervisor-present bit (`CPUID.1:ECX[31]`) and a vendor block at
+//! [`AERO_CPUID_BASE_LEAF`], offset from `0x4000_0000` so Hyper-V style leaves can occupy the
+//! bottom of the hypervisor range later:
+//!
+//! | leaf         | EAX                           | EBX              | ECX                 | EDX                |
+//! |--------------|-------------------------------|------------------|---------------------|--------------------|
+//! | `0x40000100` | last Aero leaf (`0x40000101`) | `"Aero"`         | `"Aero"`            | `0`                |
+//! | `0x40000101` | [`AERO_MACHINE_ABI_VERSION`]  | [`feature_bits`] | input-batch version | [`AERO_MSR_BASE`]  |
+//!
+//! The extended feature list is exposed as read-only MSRs: [`AERO_MSR_BASE`] holds the number of
+//! entries and `AERO_MSR_BASE + 1 + i` entry `i` (`id << 16 | version`, see [`AeroFeatureId`]).
+//! Entries are listed in id order. Reading past the last entry or writing any of the MSRs raises
+//! `#GP`.
+//!
+//! The information is rebuilt from the machine configuration on every reset and snapshot restore
+//! (and when the AeroGPU submission bridge is toggled). It is derived only from configuration and
+//! snapshotted device state, so a restored guest sees the values it saw before the snapshot.
+
+use aero_cpu_core::cpuid::{CpuidResult, HypervisorInterface};
+use aero_protocol::aerogpu::aerogpu_pci as pci;
+
+use crate::MachineConfig;
+
+/// Vendor leaf of the Aero CPUID block.
+pub const AERO_CPUID_BASE_LEAF: u32 = 0x4000_0100;
+/// Signature returned in EBX/ECX/EDX of [`AERO_CPUID_BASE_LEAF`].
+pub const AERO_CPUID_SIGNATURE: [u8; 12] = *b"AeroAero\0\0\0\0";
+/// Machine ABI version reported in `CPUID.0x40000101:EAX`; bumped on incompatible changes to
+/// guest-visible Aero interfaces.
+pub const AERO_MACHINE_ABI_VERSION: u32 = 1;
+/// Version of the [`crate::Machine::inject_input_batch`] wire format.
+pub const AERO_INPUT_BATCH_PROTOCOL_VERSION: u32 = 1;
+
+/// First extended feature list MSR (entry count); clear of the Hyper-V synthetic MSR range.
+pub const AERO_MSR_BASE: u32 = 0x4000_0200;
+
+/// `CPUID.0x40000101:EBX` bits.
+pub mod feature_bits {
+    /// An AeroGPU PCI function is present.
+    pub const AEROGPU: u32 = 1 << 0;
+    /// AeroGPU submissions are executed out of process (the submission bridge is active).
+    pub const AEROGPU_SUBMISSION_BRIDGE: u32 = 1 << 1;
+    /// At least one virtio PCI device is present.
+    pub const VIRTIO: u32 = 1 << 2;
+    /// The extended feature list MSRs are present (always set).
+    pub const EXTENDED_FEATURE_MSRS: u32 = 1 << 3;
+}
+
+/// Ids of the extended feature list entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u16)]
+pub enum AeroFeatureId {
+    /// AeroGPU; version is the AeroGPU ABI major version.
+    AeroGpu = 1,
+    AeroGpuSubmissionBridge = 2,
+    VirtioBlk = 3,
+    VirtioNet = 4,
+    VirtioInput = 5,
+    VirtioInputTablet = 6,
+    VirtioBalloon = 7,
+    Virtio9p = 8,
+    VirtioRng = 9,
+    /// Host input batches; version is [`AERO_INPUT_BATCH_PROTOCOL_VERSION`].
+    InputBatch = 10,
+    /// The aero-bench port device ([`crate::MachineConfig::enable_aero_bench`]).
+    AeroBench = 11,
+}
+
+/// What a guest can discover about the machine through CPUID and the extended feature MSRs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParavirtInfo {
+    pub abi_version: u32,
+    /// [`feature_bits`] flags.
+    pub features: u32,
+    pub input_batch_version: u32,
+    /// Extended feature list entries (`id << 16 | version`).
+    pub extended_features: Vec<u32>,
+}
+
+impl ParavirtInfo {
+    pub(crate) fn from_config(cfg: &MachineConfig, submission_bridge: bool) -> Self {
+        let mut entries: Vec<(AeroFeatureId, u16)> = Vec::new();
+        let aerogpu = cfg.enable_aerogpu && cfg.enable_pc_platform;
+        if aerogpu {
+            entries.push((AeroFeatureId::AeroGpu, pci::AEROGPU_ABI_MAJOR as u16));
+            if submission_bridge {
+                entries.push((AeroFeatureId::AeroGpuSubmissionBridge, 1));
+            }
+        }
+        if cfg.enable_pc_platform {
+            for (enabled, id) in [
+                (cfg.enable_virtio_blk, AeroFeatureId::VirtioBlk),
+                (cfg.enable_virtio_net, AeroFeatureId::VirtioNet),
+                (cfg.enable_virtio_input, AeroFeatureId::VirtioInput),
+                (
+                    cfg.enable_virtio_input && cfg.enable_virtio_input_tablet,
+                    AeroFeatureId::VirtioInputTablet,
+                ),
+                (cfg.enable_virtio_balloon, AeroFeatureId::VirtioBalloon),
+                (cfg.enable_virtio_9p, AeroFeatureId::Virtio9p),
+                (cfg.enable_virtio_rng, AeroFeatureId::VirtioRng),
+            ] {
+                if enabled {
+                    entries.push((id, 1));
+                }
+            }
+        }
+        let virtio = entries
+            .iter()
+            .any(|&(id, _)| (AeroFeatureId::VirtioBlk..=AeroFeatureId::VirtioRng).contains(&id));
+        entries.push((
+            AeroFeatureId::InputBatch,
+            AERO_INPUT_BATCH_PROTOCOL_VERSION as u16,
+        ));
+        if cfg.enable_aero_bench {
+            entries.push((AeroFeatureId::AeroBench, 1));
+        }
+
+        let mut features = feature_bits::EXTENDED_FEATURE_MSRS;
+        if aerogpu {
+            features |= feature_bits::AEROGPU;
+        }
+        if aerogpu && submission_bridge {
+            features |= feature_bits::AEROGPU_SUBMISSION_BRIDGE;
+        }
+        if virtio {
+            features |= feature_bits::VIRTIO;
+        }
+
+        Self {
+            abi_version: AERO_MACHINE_ABI_VERSION,
+            features,
+            input_batch_version: AERO_INPUT_BATCH_PROTOCOL_VERSION,
+            extended_features: entries
+                .into_iter()
+                .map(|(id, version)| (u32::from(id as u16) << 16) | u32::from(version))
+                .collect(),
+        }
+    }
+
+    /// The CPUID leaves and MSRs publishing this information.
+    pub fn hypervisor_interface(&self) -> HypervisorInterface {
+        let mut msrs = Vec::with_capacity(1 + self.extended_features.len());
+        msrs.push(self.extended_features.len() as u64);
+        msrs.extend(self.extended_features.iter().map(|&entry| u64::from(entry)));
+        HypervisorInterface {
+            base_leaf: AERO_CPUID_BASE_LEAF,
+            signature: AERO_CPUID_SIGNATURE,
+            leaves: vec![CpuidResult {
+                eax: self.abi_version,
+                ebx: self.features,
+                ecx: self.input_batch_version,
+                edx: AERO_MSR_BASE,
+            }],
+            msr_base: AERO_MSR_BASE,
+            msrs,
+        }
+    }
+}
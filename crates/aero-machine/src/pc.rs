@@ -25,7 +25,9 @@ use firmware::bios::{A20Gate, Bios, BiosBus, BiosConfig, FirmwareMemory};
 use memory::{DenseMemory, GuestMemory, MapError, SparseMemory};
 
 use crate::pci_firmware::SharedPciConfigPortsBiosAdapter;
-use crate::{GuestTime, MachineError, RunExit, SharedDisk, SPARSE_RAM_THRESHOLD_BYTES};
+use crate::{
+    GuestTime, MachineError, ResetSource, RunExit, SharedDisk, SPARSE_RAM_THRESHOLD_BYTES,
+};
 
 /// Configuration for [`PcMachine`].
 #[derive(Debug, Clone)]
@@ -581,7 +583,11 @@ impl PcMachine {
 
         while executed < max_insts {
             if let Some(kind) = self.take_reset_kind() {
-                return RunExit::ResetRequested { kind, executed };
+                return RunExit::ResetRequested {
+                    kind,
+                    source: ResetSource::Unknown,
+                    executed,
+                };
             }
 
             // Allow DMA-capable devices to make forward progress even while the CPU is halted.
@@ -599,7 +605,11 @@ impl PcMachine {
             self.poll_network();
 
             if let Some(kind) = self.take_reset_kind() {
-                return RunExit::ResetRequested { kind, executed };
+                return RunExit::ResetRequested {
+                    kind,
+                    source: ResetSource::Unknown,
+                    executed,
+                };
             }
 
             // Keep the core's A20 view coherent with the chipset latch.
@@ -635,7 +645,11 @@ impl PcMachine {
             self.tick_platform_from_cycles(batch.executed);
 
             if let Some(kind) = self.take_reset_kind() {
-                return RunExit::ResetRequested { kind, executed };
+                return RunExit::ResetRequested {
+                    kind,
+                    source: ResetSource::Unknown,
+                    executed,
+                };
             }
 
             match batch.exit {
@@ -999,7 +1013,7 @@ mod tests {
         );
 
         match pc.run_slice(16) {
-            RunExit::ResetRequested { kind, executed } => {
+            RunExit::ResetRequested { kind, executed, .. } => {
                 assert_eq!(executed, 0);
                 assert_eq!(kind, ResetKind::System);
             }
@@ -1071,7 +1085,7 @@ mod tests {
         );
 
         match pc.run_slice(16) {
-            RunExit::ResetRequested { kind, executed } => {
+            RunExit::ResetRequested { kind, executed, .. } => {
                 assert_eq!(executed, 0);
                 assert_eq!(kind, ResetKind::System);
             }
@@ -0,0 +1,162 @@
+//! Live view of the guest-visible PCI topology (see [`crate::Machine::pci_devices`]).
+//!
+//! Everything is read from the shared PCI config bus at call time, so the reported BARs, command
+//! bits, interrupt line and MSI/MSI-X enables reflect guest (or firmware) reprogramming rather
+//! than the construction-time defaults implied by [`crate::MachineConfig`].
+//!
+//! With the `serde` feature the info structs implement `serde::Serialize` so the wasm layer can
+//! hand them to JS as JSON. BDFs serialize as `"bb:dd.f"` strings and BAR kinds as lowercase
+//! names.
+
+use aero_devices::pci::config::{PciBarDefinition, PciBarKind};
+use aero_devices::pci::msi::PCI_CAP_ID_MSI;
+use aero_devices::pci::msix::PCI_CAP_ID_MSIX;
+use aero_devices::pci::{PciBdf, PciBus};
+
+/// Number of BAR slots in a type 0 configuration header.
+const PCI_BAR_COUNT: u8 = 6;
+
+/// One implemented BAR of a PCI function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PciBarInfo {
+    /// BAR slot (`0..=5`). A 64-bit BAR occupies this slot and the next one.
+    pub index: u8,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_bar_kind"))]
+    pub kind: PciBarKind,
+    /// Currently programmed base address (0 if unassigned).
+    pub base: u64,
+    pub size: u64,
+    pub prefetchable: bool,
+    /// Whether the BAR is currently decoded (assigned and enabled by the command register).
+    pub enabled: bool,
+}
+
+/// Summary of a PCI function as currently programmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PciDeviceInfo {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_bdf"))]
+    pub bdf: PciBdf,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub subsystem_vendor_id: u16,
+    pub subsystem_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision_id: u8,
+    /// Raw header type byte (bit 7 = multi-function).
+    pub header_type: u8,
+    /// PCI command register.
+    pub command: u16,
+    pub bars: Vec<PciBarInfo>,
+    /// Capability IDs in capability-list order.
+    pub capabilities: Vec<u8>,
+    pub msi_present: bool,
+    pub msi_enabled: bool,
+    pub msix_present: bool,
+    pub msix_enabled: bool,
+    /// Interrupt pin register (0 = none, 1 = INTA# .. 4 = INTD#).
+    pub interrupt_pin: u8,
+    /// Interrupt line register as programmed by firmware/the guest.
+    pub interrupt_line: u8,
+}
+
+pub(crate) fn collect(bus: &mut PciBus) -> Vec<PciDeviceInfo> {
+    let bdfs: Vec<PciBdf> = bus.iter_device_addrs().collect();
+    bdfs.into_iter()
+        .filter_map(|bdf| device_info(bus, bdf))
+        .collect()
+}
+
+fn device_info(bus: &mut PciBus, bdf: PciBdf) -> Option<PciDeviceInfo> {
+    let read = |bus: &mut PciBus, offset: u16, size: u8| bus.read_config(bdf, offset, size);
+
+    let vendor_id = read(bus, 0x00, 2) as u16;
+    let device_id = read(bus, 0x02, 2) as u16;
+    let command = read(bus, 0x04, 2) as u16;
+    let class_rev = read(bus, 0x08, 4);
+    let header_type = read(bus, 0x0E, 1) as u8;
+    let subsystem_vendor_id = read(bus, 0x2C, 2) as u16;
+    let subsystem_id = read(bus, 0x2E, 2) as u16;
+    let interrupt_line = read(bus, 0x3C, 1) as u8;
+    let interrupt_pin = read(bus, 0x3D, 1) as u8;
+
+    let mut bars = Vec::new();
+    let (capabilities, msi_cap, msix_cap) = {
+        let cfg = bus.device_config_mut(bdf)?;
+        for index in 0..PCI_BAR_COUNT {
+            let (Some(def), Some(range)) = (cfg.bar_definition(index), cfg.bar_range(index)) else {
+                continue;
+            };
+            let prefetchable = match def {
+                PciBarDefinition::Io { .. } => false,
+                PciBarDefinition::Mmio32 { prefetchable, .. }
+                | PciBarDefinition::Mmio64 { prefetchable, .. } => prefetchable,
+            };
+            bars.push(PciBarInfo {
+                index,
+                kind: range.kind,
+                base: range.base,
+                size: range.size,
+                prefetchable,
+                enabled: false,
+            });
+        }
+        let caps = cfg.capability_list();
+        let find = |id| caps.iter().find(|cap| cap.id == id).map(|cap| cap.offset);
+        (
+            caps.iter().map(|cap| cap.id).collect::<Vec<_>>(),
+            find(PCI_CAP_ID_MSI),
+            find(PCI_CAP_ID_MSIX),
+        )
+    };
+    for bar in &mut bars {
+        bar.enabled = bus.mapped_bar_range(bdf, bar.index).is_some();
+    }
+
+    // Message Control lives at cap+2: MSI Enable is bit 0, MSI-X Enable is bit 15.
+    let msi_enabled = msi_cap.is_some_and(|cap| read(bus, u16::from(cap) + 2, 2) & 1 != 0);
+    let msix_enabled =
+        msix_cap.is_some_and(|cap| read(bus, u16::from(cap) + 2, 2) & (1 << 15) != 0);
+
+    Some(PciDeviceInfo {
+        bdf,
+        vendor_id,
+        device_id,
+        subsystem_vendor_id,
+        subsystem_id,
+        class: (class_rev >> 24) as u8,
+        subclass: (class_rev >> 16) as u8,
+        prog_if: (class_rev >> 8) as u8,
+        revision_id: class_rev as u8,
+        header_type,
+        command,
+        bars,
+        capabilities,
+        msi_present: msi_cap.is_some(),
+        msi_enabled,
+        msix_present: msix_cap.is_some(),
+        msix_enabled,
+        interrupt_pin,
+        interrupt_line,
+    })
+}
+
+#[cfg(feature = "serde")]
+fn serialize_bdf<S: serde::Serializer>(bdf: &PciBdf, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(&format_args!(
+        "{:02x}:{:02x}.{}",
+        bdf.bus, bdf.device, bdf.function
+    ))
+}
+
+#[cfg(feature = "serde")]
+fn serialize_bar_kind<S: serde::Serializer>(kind: &PciBarKind, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(match kind {
+        PciBarKind::Io => "io",
+        PciBarKind::Mmio32 => "mmio32",
+        PciBarKind::Mmio64 => "mmio64",
+    })
+}
@@ -0,0 +1,230 @@
+//! Host-visible performance counters (see [`crate::Machine::perf_counters`]).
+//!
+//! Counters are plain `u64` increments on paths that already branch on the event being counted
+//! (batch exits, port I/O dispatch, MMIO window routing, device ticks), so they are cheap enough to
+//! stay enabled unconditionally. They are host-side diagnostics only: they are not part of guest
+//! state, are never snapshotted, and survive [`crate::Machine::reset`] and snapshot restore until
+//! the host calls [`crate::Machine::reset_perf_counters`].
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use aero_cpu_core::{AssistReason, Exception};
+
+/// Read/write access counts for one MMIO window or the port I/O space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessCounts {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// MMIO access counts, split by major window.
+///
+/// Windows mapped on the shared physical bus also count host-side accesses through that bus (e.g.
+/// firmware POST clearing legacy VGA memory); the LAPIC window only counts vCPU accesses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MmioPerfCounts {
+    /// Local APIC page (`0xFEE0_0000`), routed per vCPU.
+    pub lapic: AccessCounts,
+    pub ioapic: AccessCounts,
+    pub hpet: AccessCounts,
+    /// PCI BAR MMIO window (all PCI devices, including the VGA/AeroGPU BARs).
+    pub pci: AccessCounts,
+    /// Legacy VGA memory window (`0xA0000..0xC0000`).
+    pub vga_legacy: AccessCounts,
+    /// PCIe ECAM configuration window.
+    pub ecam: AccessCounts,
+}
+
+/// Number of times each platform device was ticked (or, in
+/// [`MachinePerfCounters::skipped_device_ticks`], skipped).
+///
+/// USB controllers are ticked once per elapsed guest millisecond, so their counts track guest time
+/// rather than `tick_platform` calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeviceTickCounts {
+    pub vga: u64,
+    pub acpi_pm: u64,
+    pub pit: u64,
+    pub rtc: u64,
+    /// Platform interrupt controller ticks (LAPIC timers).
+    pub lapic: u64,
+    pub hpet: u64,
+    pub aerogpu: u64,
+    pub uhci: u64,
+    pub ehci: u64,
+    pub xhci: u64,
+    /// NIC polls ([`crate::Machine::poll_network`] with an E1000 or virtio-net NIC).
+    pub network: u64,
+}
+
+/// Snapshot of the machine performance counters returned by
+/// [`crate::Machine::perf_counters`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachinePerfCounters {
+    /// Guest instructions retired, indexed by vCPU (index 0 is the BSP).
+    pub instructions: Vec<u64>,
+    /// Number of [`crate::Machine::run_slice`] calls.
+    pub run_slice_calls: u64,
+    /// Tier-0 assist exits, indexed by [`AssistReason::index`].
+    pub assist_exits: [u64; AssistReason::ALL.len()],
+    /// Exceptions that ended a CPU batch, indexed by architectural vector.
+    pub exceptions: [u64; 32],
+    /// Non-architectural memory faults that ended a CPU batch (no exception vector).
+    pub memory_faults: u64,
+    /// Guest port I/O accesses.
+    pub port_io: AccessCounts,
+    pub mmio: MmioPerfCounts,
+    /// External interrupt vectors injected into any vCPU.
+    pub interrupt_injections: u64,
+    pub device_ticks: DeviceTickCounts,
+    /// Device ticks skipped by device tick scheduling (see
+    /// [`crate::Machine::set_device_tick_scheduling`]).
+    pub skipped_device_ticks: DeviceTickCounts,
+}
+
+impl MachinePerfCounters {
+    /// Total guest instructions retired across all vCPUs.
+    pub fn total_instructions(&self) -> u64 {
+        self.instructions
+            .iter()
+            .fold(0u64, |acc, &n| acc.wrapping_add(n))
+    }
+
+    /// Number of assist exits taken for `reason`.
+    pub fn assist_exits_for(&self, reason: AssistReason) -> u64 {
+        self.assist_exits[reason.index()]
+    }
+
+    pub(crate) fn new(cpu_count: usize) -> Self {
+        Self {
+            instructions: vec![0; cpu_count],
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn record_instructions(&mut self, vcpu: usize, executed: u64) {
+        if let Some(slot) = self.instructions.get_mut(vcpu) {
+            *slot = slot.wrapping_add(executed);
+        }
+    }
+
+    pub(crate) fn record_exception(&mut self, exception: &Exception) {
+        match exception_vector(exception) {
+            Some(vector) => bump(&mut self.exceptions[usize::from(vector)]),
+            None => bump(&mut self.memory_faults),
+        }
+    }
+}
+
+/// Architectural vector a Tier-0 exception is delivered as, or `None` for bus faults.
+fn exception_vector(exception: &Exception) -> Option<u8> {
+    Some(match exception {
+        Exception::DivideError => 0,
+        Exception::InvalidOpcode | Exception::Unimplemented(_) => 6,
+        Exception::DeviceNotAvailable => 7,
+        Exception::InvalidTss(_) => 10,
+        Exception::SegmentNotPresent(_) => 11,
+        Exception::StackSegment(_) => 12,
+        Exception::GeneralProtection(_) => 13,
+        Exception::PageFault { .. } => 14,
+        Exception::X87Fpu => 16,
+        Exception::SimdFloatingPointException => 19,
+        Exception::MemoryFault => return None,
+    })
+}
+
+#[inline]
+pub(crate) fn bump(counter: &mut u64) {
+    *counter = counter.wrapping_add(1);
+}
+
+/// Shared read/write counter for one MMIO window.
+///
+/// MMIO handlers are owned by the physical memory bus, so the window counters are shared with the
+/// machine through an `Rc` rather than borrowed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MmioCounter(Rc<[Cell<u64>; 2]>);
+
+impl MmioCounter {
+    #[inline]
+    pub(crate) fn record_read(&self) {
+        self.0[0].set(self.0[0].get().wrapping_add(1));
+    }
+
+    #[inline]
+    pub(crate) fn record_write(&self) {
+        self.0[1].set(self.0[1].get().wrapping_add(1));
+    }
+
+    pub(crate) fn writes(&self) -> u64 {
+        self.0[1].get()
+    }
+
+    fn get(&self) -> AccessCounts {
+        AccessCounts {
+            reads: self.0[0].get(),
+            writes: self.0[1].get(),
+        }
+    }
+
+    fn clear(&self) {
+        self.0[0].set(0);
+        self.0[1].set(0);
+    }
+}
+
+/// Per-window MMIO counters held by the machine; see [`MmioPerfCounts`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MmioPerfCounters {
+    pub(crate) lapic: MmioCounter,
+    pub(crate) ioapic: MmioCounter,
+    pub(crate) hpet: MmioCounter,
+    pub(crate) pci: MmioCounter,
+    pub(crate) vga_legacy: MmioCounter,
+    pub(crate) ecam: MmioCounter,
+}
+
+impl MmioPerfCounters {
+    pub(crate) fn get(&self) -> MmioPerfCounts {
+        MmioPerfCounts {
+            lapic: self.lapic.get(),
+            ioapic: self.ioapic.get(),
+            hpet: self.hpet.get(),
+            pci: self.pci.get(),
+            vga_legacy: self.vga_legacy.get(),
+            ecam: self.ecam.get(),
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        for counter in [
+            &self.lapic,
+            &self.ioapic,
+            &self.hpet,
+            &self.pci,
+            &self.vga_legacy,
+            &self.ecam,
+        ] {
+            counter.clear();
+        }
+    }
+}
+
+/// Wraps an MMIO handler so every access is counted against its window.
+pub(crate) struct CountedMmio<H> {
+    pub(crate) inner: H,
+    pub(crate) counter: MmioCounter,
+}
+
+impl<H: memory::MmioHandler> memory::MmioHandler for CountedMmio<H> {
+    fn read(&mut self, offset: u64, size: usize) -> u64 {
+        self.counter.record_read();
+        self.inner.read(offset, size)
+    }
+
+    fn write(&mut self, offset: u64, size: usize, value: u64) {
+        self.counter.record_write();
+        self.inner.write(offset, size, value);
+    }
+}
@@ -0,0 +1,327 @@
+//! Host-side coalescing of relative pointer input (see [`crate::MachineConfig::pointer_coalescing`]).
+//!
+//! High-rate host mice can inject far more motion events than a guest drains between two
+//! `run_slice` calls. Each pointer path (virtio-input mouse, PS/2 mouse) keeps a small backlog of
+//! frames in front of its device: relative motion merges into the newest pending motion frame,
+//! while button transitions always start a new frame and are never merged, so press/release order
+//! is preserved exactly. Button frames are delivered as soon as they are queued (together with any
+//! motion queued before them); pending motion is delivered at the next `run_slice` boundary or once
+//! the configured guest-time window elapses.
+
+use std::collections::VecDeque;
+
+use aero_devices_input::{I8042Controller, Ps2MouseButton};
+use aero_virtio::devices::input::VirtioInput;
+
+// Host-side safety: cap the backlog when the device stays full under
+// `PointerQueueFullPolicy::Block` (guest driver stalled). Motion merges into the newest frame, so
+// only button transitions can grow the backlog this far.
+const MAX_PENDING_FRAMES: usize = 1024;
+
+/// When injected relative pointer motion is delivered to the guest device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerCoalescing {
+    /// Every injected event is forwarded to the device immediately.
+    #[default]
+    Off,
+    /// Motion injected between two `run_slice` calls is summed and delivered as one frame at the
+    /// start of the next slice.
+    PerSlice,
+    /// Motion is summed until `window_ns` of guest time has elapsed since the oldest pending
+    /// frame; the merged frame is delivered by the first injection or `run_slice` after that.
+    Window { window_ns: u64 },
+}
+
+/// What to do when the guest device has no room for the next pending frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerQueueFullPolicy {
+    /// Discard the oldest motion-only frame (first from the device queue, then from the host
+    /// backlog). Button transitions are never discarded; they wait for the guest to drain.
+    #[default]
+    DropOldestMotion,
+    /// Keep everything in the host backlog until the guest drains the device queue.
+    Block,
+}
+
+/// Per-path pointer input telemetry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PointerEventCounters {
+    /// Host injection calls accepted (motion, wheel and button).
+    pub injected: u64,
+    /// Motion injections merged into an already pending frame.
+    pub coalesced: u64,
+    /// Frames discarded because the device queue (or, under [`PointerQueueFullPolicy::Block`],
+    /// the bounded host backlog) was full.
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PointerFrame<B> {
+    Motion {
+        dx: i32,
+        dy: i32,
+        wheel: i32,
+        hwheel: i32,
+    },
+    Button {
+        button: B,
+        pressed: bool,
+    },
+}
+
+/// Device side of a pointer path.
+pub(crate) trait PointerSink<B> {
+    /// Hand `frame` to the device. Returns `false` (leaving the device untouched) if the device
+    /// queue has no room for the whole frame.
+    fn try_deliver(&mut self, frame: &PointerFrame<B>) -> bool;
+
+    /// Evict the oldest motion-only frame still queued in the device. Returns whether one was
+    /// found.
+    fn evict_oldest_motion(&mut self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PointerBacklog<B> {
+    frames: VecDeque<PointerFrame<B>>,
+    // Guest time at which the oldest pending frame was queued.
+    oldest_ns: Option<u64>,
+    counters: PointerEventCounters,
+}
+
+impl<B> Default for PointerBacklog<B> {
+    fn default() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            oldest_ns: None,
+            counters: PointerEventCounters::default(),
+        }
+    }
+}
+
+impl<B: Copy> PointerBacklog<B> {
+    pub(crate) fn counters(&self) -> PointerEventCounters {
+        self.counters
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub(crate) fn has_pending_buttons(&self) -> bool {
+        self.frames
+            .iter()
+            .any(|frame| matches!(frame, PointerFrame::Button { .. }))
+    }
+
+    /// Drop pending frames (e.g. on machine reset); counters are kept.
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+        self.oldest_ns = None;
+    }
+
+    pub(crate) fn push_motion(
+        &mut self,
+        mode: PointerCoalescing,
+        now_ns: u64,
+        dx: i32,
+        dy: i32,
+        wheel: i32,
+        hwheel: i32,
+    ) {
+        self.counters.injected = self.counters.injected.wrapping_add(1);
+        if mode != PointerCoalescing::Off {
+            if let Some(PointerFrame::Motion {
+                dx: pdx,
+                dy: pdy,
+                wheel: pwheel,
+                hwheel: phwheel,
+            }) = self.frames.back_mut()
+            {
+                // Host input is untrusted; saturate rather than overflow.
+                *pdx = pdx.saturating_add(dx);
+                *pdy = pdy.saturating_add(dy);
+                *pwheel = pwheel.saturating_add(wheel);
+                *phwheel = phwheel.saturating_add(hwheel);
+                self.counters.coalesced = self.counters.coalesced.wrapping_add(1);
+                return;
+            }
+        }
+        self.push_frame(
+            now_ns,
+            PointerFrame::Motion {
+                dx,
+                dy,
+                wheel,
+                hwheel,
+            },
+        );
+    }
+
+    pub(crate) fn push_button(&mut self, now_ns: u64, button: B, pressed: bool) {
+        self.counters.injected = self.counters.injected.wrapping_add(1);
+        self.push_frame(now_ns, PointerFrame::Button { button, pressed });
+    }
+
+    fn push_frame(&mut self, now_ns: u64, frame: PointerFrame<B>) {
+        if self.frames.len() >= MAX_PENDING_FRAMES {
+            let victim = self
+                .frames
+                .iter()
+                .position(|frame| matches!(frame, PointerFrame::Motion { .. }))
+                .unwrap_or(0);
+            self.frames.remove(victim);
+            self.counters.dropped = self.counters.dropped.wrapping_add(1);
+        }
+        self.frames.push_back(frame);
+        self.oldest_ns.get_or_insert(now_ns);
+    }
+
+    /// Whether pending frames should be handed to the device now (outside a slice boundary).
+    pub(crate) fn is_due(&self, mode: PointerCoalescing, now_ns: u64) -> bool {
+        let Some(oldest_ns) = self.oldest_ns else {
+            return false;
+        };
+        match mode {
+            PointerCoalescing::Off => true,
+            _ if self.has_pending_buttons() => true,
+            PointerCoalescing::PerSlice => false,
+            PointerCoalescing::Window { window_ns } => {
+                now_ns.saturating_sub(oldest_ns) >= window_ns
+            }
+        }
+    }
+
+    /// Deliver pending frames in order until the backlog is empty or the device refuses a frame
+    /// that `policy` does not allow dropping. Returns whether anything reached the device.
+    pub(crate) fn flush(
+        &mut self,
+        policy: PointerQueueFullPolicy,
+        now_ns: u64,
+        sink: &mut impl PointerSink<B>,
+    ) -> bool {
+        let mut delivered = false;
+        while let Some(frame) = self.frames.front() {
+            if sink.try_deliver(frame) {
+                self.frames.pop_front();
+                delivered = true;
+                continue;
+            }
+            if policy == PointerQueueFullPolicy::Block {
+                break;
+            }
+            if sink.evict_oldest_motion() {
+                self.counters.dropped = self.counters.dropped.wrapping_add(1);
+                continue;
+            }
+            if matches!(frame, PointerFrame::Motion { .. }) {
+                self.frames.pop_front();
+                self.counters.dropped = self.counters.dropped.wrapping_add(1);
+                continue;
+            }
+            break;
+        }
+        // Frames left behind by a full device are retried at the next opportunity, starting a new
+        // coalescing window.
+        self.oldest_ns = (!self.frames.is_empty()).then_some(now_ns);
+        delivered
+    }
+}
+
+/// virtio-input mouse: one `EV_REL` frame per motion frame, `EV_KEY` + `SYN_REPORT` per button.
+pub(crate) struct VirtioMouseSink<'a>(pub(crate) &'a mut VirtioInput);
+
+impl PointerSink<u16> for VirtioMouseSink<'_> {
+    fn try_deliver(&mut self, frame: &PointerFrame<u16>) -> bool {
+        match *frame {
+            PointerFrame::Motion {
+                dx,
+                dy,
+                wheel,
+                hwheel,
+            } => {
+                let axes = [dx, dy, wheel, hwheel]
+                    .iter()
+                    .filter(|&&value| value != 0)
+                    .count();
+                if axes == 0 {
+                    return true;
+                }
+                if self.0.pending_events_free() < axes + 1 {
+                    return false;
+                }
+                self.0.inject_rel_frame(dx, dy, wheel, hwheel);
+            }
+            PointerFrame::Button { button, pressed } => {
+                if self.0.pending_events_free() < 2 {
+                    return false;
+                }
+                self.0.inject_button(button, pressed);
+            }
+        }
+        true
+    }
+
+    fn evict_oldest_motion(&mut self) -> bool {
+        self.0.drop_oldest_rel_frame()
+    }
+}
+
+/// PS/2 mouse behind the i8042. Horizontal wheel motion follows in separate packets (reported only
+/// once the guest enabled IntelliMouse Explorer 4.0 horizontal scrolling).
+pub(crate) struct Ps2MouseSink<'a>(pub(crate) &'a mut I8042Controller);
+
+impl Ps2MouseSink<'_> {
+    // Worst case output for one frame: the mouse splits motion into packets of at most 127 counts
+    // per axis (7 for the wheel), each up to 4 bytes with the IntelliMouse extension, followed by
+    // 4-byte horizontal wheel packets of at most 31 counts.
+    const MAX_PACKET_BYTES: usize = 4;
+    const MAX_PACKETS: u32 = 128;
+
+    fn motion_bytes(dx: i32, dy: i32, wheel: i32, hwheel: i32) -> usize {
+        let packets = dx
+            .unsigned_abs()
+            .div_ceil(127)
+            .max(dy.unsigned_abs().div_ceil(127))
+            .max(wheel.unsigned_abs().div_ceil(7))
+            .min(Self::MAX_PACKETS);
+        let hwheel_packets = hwheel.unsigned_abs().div_ceil(31).min(Self::MAX_PACKETS);
+        (packets + hwheel_packets) as usize * Self::MAX_PACKET_BYTES
+    }
+}
+
+impl PointerSink<Ps2MouseButton> for Ps2MouseSink<'_> {
+    fn try_deliver(&mut self, frame: &PointerFrame<Ps2MouseButton>) -> bool {
+        let free = self.0.mouse().output_free();
+        match *frame {
+            PointerFrame::Motion {
+                dx,
+                dy,
+                wheel,
+                hwheel,
+            } => {
+                let needed = Self::motion_bytes(dx, dy, wheel, hwheel);
+                if needed == 0 {
+                    return true;
+                }
+                if free < needed {
+                    return false;
+                }
+                if dx != 0 || dy != 0 || wheel != 0 {
+                    self.0.inject_mouse_motion(dx, dy, wheel);
+                }
+                if hwheel != 0 {
+                    self.0.inject_mouse_hwheel(hwheel);
+                }
+            }
+            PointerFrame::Button { button, pressed } => {
+                if free < Self::MAX_PACKET_BYTES {
+                    return false;
+                }
+                self.0.inject_mouse_button(button, pressed);
+            }
+        }
+        true
+    }
+}
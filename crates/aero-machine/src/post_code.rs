@@ -0,0 +1,113 @@
+//! Port `0x80` POST code capture (see [`crate::Machine::post_code_history`]).
+//!
+//! Codes come from two places: the HLE BIOS, which queues one per POST phase (decodable with
+//! [`firmware::bios::BiosPostPhase`]) and is drained by the machine after each firmware call, and
+//! guest code writing port `0x80` directly. Both land in one bounded history in arrival order.
+//!
+//! On the PC platform port `0x80` is also a DMA page register; the capture device wraps it so
+//! guest writes still reach the DMA controller.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use aero_platform::io::{IoPortBus, PortIoDevice};
+use firmware::bios::POST_CODE_PORT;
+
+use crate::machine_events::MachineEventLog;
+
+/// Number of POST codes retained by [`crate::Machine::post_code_history`]; older codes are
+/// discarded first.
+pub const POST_CODE_HISTORY_CAPACITY: usize = 256;
+
+/// One code written to port `0x80`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostCodeRecord {
+    pub code: u8,
+    /// Guest time at which the code was written.
+    pub guest_time_ns: u64,
+}
+
+/// Shared between the machine and the port `0x80` device.
+#[derive(Debug, Default)]
+pub(crate) struct PostCodeLog {
+    last: Cell<Option<u8>>,
+    history: RefCell<VecDeque<PostCodeRecord>>,
+}
+
+impl PostCodeLog {
+    pub(crate) fn record(&self, code: u8, guest_time_ns: u64) {
+        self.last.set(Some(code));
+        let mut history = self.history.borrow_mut();
+        if history.len() == POST_CODE_HISTORY_CAPACITY {
+            history.pop_front();
+        }
+        history.push_back(PostCodeRecord {
+            code,
+            guest_time_ns,
+        });
+    }
+
+    pub(crate) fn last(&self) -> Option<u8> {
+        self.last.get()
+    }
+
+    pub(crate) fn history(&self) -> Vec<PostCodeRecord> {
+        self.history.borrow().iter().copied().collect()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.last.set(None);
+        self.history.borrow_mut().clear();
+    }
+}
+
+struct PostCodePort {
+    inner: Option<Box<dyn PortIoDevice>>,
+    log: Rc<PostCodeLog>,
+    events: Rc<MachineEventLog>,
+}
+
+impl PortIoDevice for PostCodePort {
+    fn read(&mut self, port: u16, size: u8) -> u32 {
+        match &mut self.inner {
+            Some(inner) => inner.read(port, size),
+            // Like most chipsets, read back the last code written.
+            None => u32::from(self.log.last().unwrap_or(0xFF)),
+        }
+    }
+
+    fn write(&mut self, port: u16, size: u8, value: u32) {
+        if let Some(inner) = &mut self.inner {
+            inner.write(port, size, value);
+        }
+        // Wider writes starting at 0x80 still post their low byte.
+        self.log.record(value as u8, self.events.now_ns());
+    }
+
+    fn reset(&mut self) {
+        if let Some(inner) = &mut self.inner {
+            inner.reset();
+        }
+    }
+
+    fn debug_name(&self) -> &'static str {
+        // Keep resource claims attributed to the wrapped device (e.g. the DMA controller).
+        self.inner
+            .as_ref()
+            .map_or("post-code", |inner| inner.debug_name())
+    }
+}
+
+/// Claim port `0x80`, wrapping whichever device already owns it.
+pub(crate) fn install_post_code_port(
+    io: &mut IoPortBus,
+    log: Rc<PostCodeLog>,
+    events: Rc<MachineEventLog>,
+) {
+    let inner = io.unregister(POST_CODE_PORT);
+    io.register(
+        POST_CODE_PORT,
+        Box::new(PostCodePort { inner, log, events }),
+    );
+}
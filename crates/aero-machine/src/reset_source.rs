@@ -0,0 +1,68 @@
+//! Attribution of guest reset requests (see [`crate::RunExit::ResetRequested`]).
+//!
+//! Every device that can reset the machine gets its own [`SourcedResetSink`], which forwards the
+//! request into the machine's [`ResetLatch`] and records which device asked. The latch keeps its
+//! "system reset wins" merging; the recorded source is the one whose request determined the latched
+//! kind (the first request, or the first system reset after CPU-only ones).
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub use aero_cpu_core::interrupts::ExceptionRecord;
+use aero_platform::reset::{PlatformResetSink, ResetKind, ResetLatch};
+
+/// What caused a guest reset request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResetSource {
+    /// A write to the reset control register at port `0xCF9`.
+    Port0xCF9,
+    /// The i8042 keyboard controller pulsed the reset line (command `0xFE`, or an output port
+    /// write clearing bit 0).
+    KeyboardController,
+    /// The fast reset bit of system control port A (`0x92`).
+    Port0x92,
+    /// The ACPI reset register. The generated FADT places `RESET_REG` at port `0xCF9`, so resets
+    /// through it are currently reported as [`ResetSource::Port0xCF9`].
+    Acpi,
+    /// The BSP failed to deliver a double fault.
+    ///
+    /// `vector_history` holds the last exception delivery attempts up to and including the one
+    /// that failed, oldest first (see [`aero_cpu_core::interrupts::EXCEPTION_HISTORY_LEN`]).
+    TripleFault {
+        vector_history: Vec<ExceptionRecord>,
+    },
+    /// A PCI function level reset. No emulated device implements FLR yet.
+    PciFunctionLevel,
+    /// The request came through a path that does not record its origin ([`crate::PcMachine`]
+    /// reports this for every reset).
+    Unknown,
+}
+
+pub(crate) type PendingResetSource = Rc<RefCell<Option<ResetSource>>>;
+
+/// Reset sink handed to one device; see the module docs.
+pub(crate) struct SourcedResetSink {
+    latch: ResetLatch,
+    pending: PendingResetSource,
+    source: ResetSource,
+}
+
+impl SourcedResetSink {
+    pub(crate) fn new(latch: ResetLatch, pending: PendingResetSource, source: ResetSource) -> Self {
+        Self {
+            latch,
+            pending,
+            source,
+        }
+    }
+}
+
+impl PlatformResetSink for SourcedResetSink {
+    fn request_reset(&mut self, kind: ResetKind) {
+        let before = self.latch.peek();
+        self.latch.request_reset(kind);
+        if self.latch.peek() != before {
+            *self.pending.borrow_mut() = Some(self.source.clone());
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! Debug view of the platform resources claimed by devices, cross-checked against what the
+//! firmware's ACPI tables advertise (see [`crate::Machine::debug_resource_map`]).
+//!
+//! Claims are collected from the live machine wiring (I/O port bus, MMIO mappings, PCI BARs and
+//! interrupt lines); the ACPI side is decoded from the tables published in guest memory. The audit
+//! only flags inconsistencies an OS would act on:
+//!
+//! - Fixed I/O decoders at or above `0x1000` (where OSes allocate PCI I/O BARs) must be reserved by
+//!   a consumer descriptor; ports below that are legacy ISA space and need no description.
+//! - Fixed MMIO decoders above 1MiB must lie inside a region ACPI describes (MADT, HPET, MCFG or a
+//!   `_CRS` memory descriptor).
+//! - Every fixed I/O, MMIO, IRQ and DMA resource ACPI advertises must have a device behind it.
+//! - PCI BARs must sit inside the root bridge windows and clear of fixed reservations.
+//! - FADT `SCI_INT` and `_PRT` must match the ACPI PM SCI line and the PIRQ routing.
+
+use std::fmt;
+
+use aero_acpi::{AcpiClaim, AcpiParseError, AcpiResource, AcpiResourceSummary};
+use aero_devices::pci::PciBdf;
+
+/// A platform resource decoded or raised by a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// I/O ports `start..=end`.
+    Io { start: u16, end: u16 },
+    /// Physical addresses `start..=end`.
+    Mmio { start: u64, end: u64 },
+    /// ISA IRQ / GSI.
+    Irq(u32),
+    /// ISA DMA channel.
+    Dma(u8),
+}
+
+/// A resource together with the device model that owns it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceClaim {
+    /// Device model name, e.g. `"pit8254"`. PCI function resources use `"pci-bar"` and
+    /// `"pci-intx"` and carry the function in [`ResourceClaim::pci`].
+    pub owner: &'static str,
+    pub pci: Option<PciBdf>,
+    pub resource: Resource,
+}
+
+/// The INTx routing of one PCI function as seen by the platform router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciIntxRoute {
+    pub bdf: PciBdf,
+    /// Interrupt pin, 0 = INTA# .. 3 = INTD#.
+    pub pin: u8,
+    /// GSI the pin is currently routed to (`None` if its PIRQ is disabled).
+    pub gsi: Option<u32>,
+}
+
+/// An inconsistency between the device claims and the ACPI tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceMismatch {
+    /// The ACPI tables could not be decoded.
+    AcpiUnreadable(AcpiParseError),
+    /// A device decodes a resource the ACPI tables neither describe nor reserve.
+    Undescribed(ResourceClaim),
+    /// The ACPI tables advertise a resource no device decodes.
+    Unclaimed(AcpiClaim),
+    /// A PCI BAR lies outside the windows the PCI root bridge `_CRS` produces.
+    PciBarOutsideWindow(ResourceClaim),
+    /// A PCI BAR overlaps a resource ACPI reserves for a fixed device.
+    PciBarConflict { bar: ResourceClaim, acpi: AcpiClaim },
+    /// FADT `SCI_INT` differs from the IRQ the ACPI PM block raises.
+    SciIrq {
+        acpi: Option<u32>,
+        device: Option<u32>,
+    },
+    /// `_PRT` maps a PCI function's pin to a different GSI than the INTx router.
+    PrtRoute {
+        bdf: PciBdf,
+        pin: u8,
+        acpi: Option<u32>,
+        router: Option<u32>,
+    },
+}
+
+impl fmt::Display for ResourceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceMismatch::AcpiUnreadable(err) => write!(f, "ACPI tables unreadable: {err}"),
+            ResourceMismatch::Undescribed(claim) => {
+                write!(f, "{claim} is not described by ACPI")
+            }
+            ResourceMismatch::Unclaimed(acpi) => write!(
+                f,
+                "{} advertises {:?} but no device decodes it",
+                acpi.source, acpi.resource
+            ),
+            ResourceMismatch::PciBarOutsideWindow(claim) => {
+                write!(f, "{claim} is outside the PCI root bridge windows")
+            }
+            ResourceMismatch::PciBarConflict { bar, acpi } => write!(
+                f,
+                "{bar} overlaps {:?} reserved by {}",
+                acpi.resource, acpi.source
+            ),
+            ResourceMismatch::SciIrq { acpi, device } => write!(
+                f,
+                "FADT SCI_INT is {acpi:?} but the ACPI PM block raises IRQ {device:?}"
+            ),
+            ResourceMismatch::PrtRoute {
+                bdf,
+                pin,
+                acpi,
+                router,
+            } => write!(
+                f,
+                "_PRT routes {:02x}:{:02x}.{} INT{}# to GSI {acpi:?} but the router uses {router:?}",
+                bdf.bus,
+                bdf.device,
+                bdf.function,
+                char::from(b'A' + pin)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ResourceClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.owner)?;
+        if let Some(bdf) = self.pci {
+            write!(f, " {:02x}:{:02x}.{}", bdf.bus, bdf.device, bdf.function)?;
+        }
+        match self.resource {
+            Resource::Io { start, end } => write!(f, " I/O {start:#06x}..={end:#06x}"),
+            Resource::Mmio { start, end } => write!(f, " MMIO {start:#x}..={end:#x}"),
+            Resource::Irq(irq) => write!(f, " IRQ {irq}"),
+            Resource::Dma(channel) => write!(f, " DMA {channel}"),
+        }
+    }
+}
+
+/// Structured description of every resource devices claim, alongside the ACPI view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceMap {
+    pub claims: Vec<ResourceClaim>,
+    pub pci_intx: Vec<PciIntxRoute>,
+    /// Resources the published ACPI tables advertise (`None` if no tables are published).
+    pub acpi: Option<AcpiResourceSummary>,
+    pub mismatches: Vec<ResourceMismatch>,
+}
+
+impl ResourceMap {
+    pub(crate) fn new(
+        claims: Vec<ResourceClaim>,
+        pci_intx: Vec<PciIntxRoute>,
+        acpi: Option<Result<AcpiResourceSummary, AcpiParseError>>,
+    ) -> Self {
+        let (acpi, mismatches) = match acpi {
+            None => (None, Vec::new()),
+            Some(Err(err)) => (None, vec![ResourceMismatch::AcpiUnreadable(err)]),
+            Some(Ok(acpi)) => {
+                let mismatches = audit(&claims, &pci_intx, &acpi);
+                (Some(acpi), mismatches)
+            }
+        };
+        Self {
+            claims,
+            pci_intx,
+            acpi,
+            mismatches,
+        }
+    }
+}
+
+/// Owners whose claims are windows routing to PCI BARs, audited via the BARs themselves.
+const PCI_WINDOW_OWNERS: [&str; 2] = ["pci-io-bar-window", "pci-mmio-window"];
+
+/// First I/O port OSes hand out to PCI I/O BARs.
+const PCI_IO_ALLOC_START: u16 = 0x1000;
+
+const HIGH_MMIO_START: u64 = 0x10_0000;
+
+fn overlaps(a_start: u64, a_end: u64, b_start: u64, b_len: u64) -> bool {
+    b_len != 0 && a_start < b_start + b_len && b_start <= a_end
+}
+
+fn contains(start: u64, end: u64, base: u64, len: u64) -> bool {
+    len != 0 && start >= base && end < base + len
+}
+
+pub(crate) fn audit(
+    claims: &[ResourceClaim],
+    pci_intx: &[PciIntxRoute],
+    acpi: &AcpiResourceSummary,
+) -> Vec<ResourceMismatch> {
+    let mut out = Vec::new();
+
+    let fixed = || {
+        claims
+            .iter()
+            .filter(|c| c.pci.is_none() && !PCI_WINDOW_OWNERS.contains(&c.owner))
+    };
+    let acpi_io = || {
+        acpi.claims.iter().filter_map(|c| match c.resource {
+            AcpiResource::Io { base, len } => Some((c, u64::from(base), u64::from(len))),
+            _ => None,
+        })
+    };
+    let acpi_mem = || {
+        acpi.claims.iter().filter_map(|c| match c.resource {
+            AcpiResource::Memory { base, len } => Some((c, base, len)),
+            _ => None,
+        })
+    };
+    let io_windows = || {
+        acpi.claims.iter().filter_map(|c| match c.resource {
+            AcpiResource::IoWindow { base, len } => Some((base, len)),
+            _ => None,
+        })
+    };
+    let mem_windows = || {
+        acpi.claims.iter().filter_map(|c| match c.resource {
+            AcpiResource::MemoryWindow { base, len } => Some((base, len)),
+            _ => None,
+        })
+    };
+
+    // Devices -> ACPI.
+    for claim in fixed() {
+        match claim.resource {
+            Resource::Io { start, end } if end >= PCI_IO_ALLOC_START => {
+                let start = start.max(PCI_IO_ALLOC_START);
+                let reserved = (start..=end).all(|port| {
+                    acpi_io().any(|(_, base, len)| contains(port.into(), port.into(), base, len))
+                });
+                if !reserved {
+                    out.push(ResourceMismatch::Undescribed(claim.clone()));
+                }
+            }
+            Resource::Mmio { start, end } if start >= HIGH_MMIO_START => {
+                if !acpi_mem().any(|(_, base, len)| contains(start, end, base, len)) {
+                    out.push(ResourceMismatch::Undescribed(claim.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // ACPI -> devices.
+    for acpi_claim in &acpi.claims {
+        let decoded = match acpi_claim.resource {
+            AcpiResource::Io { base, len } => fixed().any(|c| match c.resource {
+                Resource::Io { start, end } => {
+                    overlaps(start.into(), end.into(), base.into(), len.into())
+                }
+                _ => false,
+            }),
+            AcpiResource::Memory { base, len } => fixed().any(|c| match c.resource {
+                Resource::Mmio { start, end } => overlaps(start, end, base, len),
+                _ => false,
+            }),
+            AcpiResource::Irq(irq) => fixed().any(|c| c.resource == Resource::Irq(irq)),
+            AcpiResource::Dma(channel) => fixed().any(|c| c.resource == Resource::Dma(channel)),
+            AcpiResource::IoWindow { .. }
+            | AcpiResource::MemoryWindow { .. }
+            | AcpiResource::BusRange { .. } => true,
+        };
+        if !decoded {
+            out.push(ResourceMismatch::Unclaimed(acpi_claim.clone()));
+        }
+    }
+
+    // PCI BARs.
+    for bar in claims.iter().filter(|c| c.owner == "pci-bar") {
+        let (in_window, conflict) = match bar.resource {
+            Resource::Io { start, end } => {
+                let (start, end) = (u64::from(start), u64::from(end));
+                (
+                    io_windows().any(|(base, len)| contains(start, end, base, len)),
+                    acpi_io().find(|&(_, base, len)| overlaps(start, end, base, len)),
+                )
+            }
+            Resource::Mmio { start, end } => (
+                mem_windows().any(|(base, len)| contains(start, end, base, len)),
+                acpi_mem().find(|&(_, base, len)| overlaps(start, end, base, len)),
+            ),
+            _ => continue,
+        };
+        if !in_window {
+            out.push(ResourceMismatch::PciBarOutsideWindow(bar.clone()));
+        }
+        if let Some((acpi_claim, _, _)) = conflict {
+            out.push(ResourceMismatch::PciBarConflict {
+                bar: bar.clone(),
+                acpi: acpi_claim.clone(),
+            });
+        }
+    }
+
+    // Interrupt routing.
+    let sci = fixed().find_map(|c| match c.resource {
+        Resource::Irq(irq) if c.owner == "acpi-pm" => Some(irq),
+        _ => None,
+    });
+    if acpi.sci_irq != sci {
+        out.push(ResourceMismatch::SciIrq {
+            acpi: acpi.sci_irq,
+            device: sci,
+        });
+    }
+    for route in pci_intx.iter().filter(|r| r.bdf.bus == 0) {
+        let prt = acpi
+            .prt
+            .iter()
+            .find(|e| e.device == route.bdf.device && e.pin == route.pin)
+            .map(|e| e.gsi);
+        if prt != route.gsi {
+            out.push(ResourceMismatch::PrtRoute {
+                bdf: route.bdf,
+                pin: route.pin,
+                acpi: prt,
+                router: route.gsi,
+            });
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aero_acpi::AcpiPrtEntry;
+
+    fn fixed(owner: &'static str, resource: Resource) -> ResourceClaim {
+        ResourceClaim {
+            owner,
+            pci: None,
+            resource,
+        }
+    }
+
+    fn acpi(source: &str, resource: AcpiResource) -> AcpiClaim {
+        AcpiClaim {
+            source: source.to_string(),
+            resource,
+        }
+    }
+
+    #[test]
+    fn consistent_platform_has_no_mismatches() {
+        let claims = vec![
+            fixed(
+                "acpi-pm",
+                Resource::Io {
+                    start: 0x400,
+                    end: 0x403,
+                },
+            ),
+            fixed("acpi-pm", Resource::Irq(9)),
+            fixed(
+                "hpet",
+                Resource::Mmio {
+                    start: 0xFED0_0000,
+                    end: 0xFED0_03FF,
+                },
+            ),
+            // Legacy ISA ports need no ACPI description.
+            fixed(
+                "serial16550",
+                Resource::Io {
+                    start: 0x3F8,
+                    end: 0x3FF,
+                },
+            ),
+            fixed(
+                "pci-io-bar-window",
+                Resource::Io {
+                    start: 0x1000,
+                    end: 0xEFFF,
+                },
+            ),
+            ResourceClaim {
+                owner: "pci-bar",
+                pci: Some(PciBdf::new(0, 2, 0)),
+                resource: Resource::Mmio {
+                    start: 0xC000_0000,
+                    end: 0xC000_0FFF,
+                },
+            },
+        ];
+        let summary = AcpiResourceSummary {
+            claims: vec![
+                acpi(
+                    "FADT.PM1a_EVT_BLK",
+                    AcpiResource::Io {
+                        base: 0x400,
+                        len: 4,
+                    },
+                ),
+                acpi(
+                    "HPET.BaseAddress",
+                    AcpiResource::Memory {
+                        base: 0xFED0_0000,
+                        len: 0x400,
+                    },
+                ),
+                acpi(
+                    "\\_SB_.PCI0._CRS",
+                    AcpiResource::MemoryWindow {
+                        base: 0xC000_0000,
+                        len: 0x3EC0_0000,
+                    },
+                ),
+            ],
+            prt: vec![AcpiPrtEntry {
+                device: 2,
+                pin: 0,
+                gsi: 12,
+            }],
+            sci_irq: Some(9),
+        };
+        let intx = [PciIntxRoute {
+            bdf: PciBdf::new(0, 2, 0),
+            pin: 0,
+            gsi: Some(12),
+        }];
+        assert_eq!(audit(&claims, &intx, &summary), vec![]);
+    }
+
+    #[test]
+    fn flags_mismatches_in_both_directions() {
+        let claims = vec![
+            fixed(
+                "debugcon",
+                Resource::Io {
+                    start: 0x2000,
+                    end: 0x2000,
+                },
+            ),
+            fixed("acpi-pm", Resource::Irq(10)),
+            ResourceClaim {
+                owner: "pci-bar",
+                pci: Some(PciBdf::new(0, 3, 0)),
+                resource: Resource::Io {
+                    start: 0x400,
+                    end: 0x41F,
+                },
+            },
+        ];
+        let summary = AcpiResourceSummary {
+            claims: vec![
+                acpi(
+                    "FADT.PM1a_EVT_BLK",
+                    AcpiResource::Io {
+                        base: 0x400,
+                        len: 4,
+                    },
+                ),
+                acpi("\\_SB_.RTC_._CRS", AcpiResource::Irq(8)),
+                acpi(
+                    "\\_SB_.PCI0._CRS",
+                    AcpiResource::IoWindow {
+                        base: 0xD00,
+                        len: 0xF300,
+                    },
+                ),
+            ],
+            prt: vec![],
+            sci_irq: Some(9),
+        };
+        let intx = [PciIntxRoute {
+            bdf: PciBdf::new(0, 3, 0),
+            pin: 0,
+            gsi: Some(13),
+        }];
+
+        assert_eq!(
+            audit(&claims, &intx, &summary),
+            vec![
+                ResourceMismatch::Undescribed(claims[0].clone()),
+                ResourceMismatch::Unclaimed(summary.claims[0].clone()),
+                ResourceMismatch::Unclaimed(summary.claims[1].clone()),
+                ResourceMismatch::PciBarOutsideWindow(claims[2].clone()),
+                ResourceMismatch::PciBarConflict {
+                    bar: claims[2].clone(),
+                    acpi: summary.claims[0].clone(),
+                },
+                ResourceMismatch::SciIrq {
+                    acpi: Some(9),
+                    device: Some(10),
+                },
+                ResourceMismatch::PrtRoute {
+                    bdf: PciBdf::new(0, 3, 0),
+                    pin: 0,
+                    acpi: None,
+                    router: Some(13),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Guest-time run budgets (see [`crate::Machine::run_for`] and
+//! [`crate::Machine::run_until_deadline`]).
+//!
+//! A budgeted run is a sequence of internal `run_slice` batches with the stop condition (elapsed
+//! guest time, or a host callback) checked between them, so a run can only overshoot its budget by
+//! the last batch. Batches start at [`RUN_BUDGET_MIN_BATCH_INSTS`] and double up to
+//! [`RUN_BUDGET_MAX_BATCH_INSTS`]; with a time budget each batch is additionally capped to the
+//! instructions that fit in the remaining time at the TSC frequency (Tier-0 retires one cycle per
+//! instruction), which keeps short budgets tight.
+
+/// Instruction count of the first internal batch of a budgeted run.
+pub const RUN_BUDGET_MIN_BATCH_INSTS: u64 = 1024;
+
+/// Upper bound on the instruction count of any internal batch of a budgeted run.
+pub const RUN_BUDGET_MAX_BATCH_INSTS: u64 = 1 << 20;
+
+const NS_PER_SEC: u128 = 1_000_000_000;
+
+/// Adaptive batch size of a budgeted run.
+#[derive(Debug)]
+pub(crate) struct BatchSizer {
+    next: u64,
+}
+
+impl BatchSizer {
+    pub(crate) fn new() -> Self {
+        Self {
+            next: RUN_BUDGET_MIN_BATCH_INSTS,
+        }
+    }
+
+    /// Instruction count of the next batch, given the guest time left in the budget (if any) and
+    /// the BSP's TSC frequency.
+    pub(crate) fn next_batch(&mut self, remaining_ns: Option<u64>, tsc_hz: u64) -> u64 {
+        let mut batch = self.next;
+        self.next = (self.next * 2).min(RUN_BUDGET_MAX_BATCH_INSTS);
+        if let Some(remaining_ns) = remaining_ns.filter(|_| tsc_hz != 0) {
+            let fit = (u128::from(remaining_ns) * u128::from(tsc_hz)).div_ceil(NS_PER_SEC);
+            batch = batch.min(u64::try_from(fit).unwrap_or(u64::MAX).max(1));
+        }
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_grow_geometrically_up_to_the_cap() {
+        let mut sizer = BatchSizer::new();
+        let batches: Vec<u64> = (0..12).map(|_| sizer.next_batch(None, 0)).collect();
+        assert_eq!(batches[0], RUN_BUDGET_MIN_BATCH_INSTS);
+        assert_eq!(batches[1], 2 * RUN_BUDGET_MIN_BATCH_INSTS);
+        assert_eq!(batches[11], RUN_BUDGET_MAX_BATCH_INSTS);
+    }
+
+    #[test]
+    fn batches_are_capped_to_the_remaining_budget() {
+        let mut sizer = BatchSizer::new();
+        // 100ns at 3GHz is 300 cycles.
+        assert_eq!(sizer.next_batch(Some(100), 3_000_000_000), 300);
+        // A partial nanosecond still runs at least one instruction.
+        assert_eq!(sizer.next_batch(Some(0), 3_000_000_000), 1);
+        // An unknown TSC frequency falls back to the adaptive size.
+        assert_eq!(sizer.next_batch(Some(1), 0), 4 * RUN_BUDGET_MIN_BATCH_INSTS);
+    }
+}
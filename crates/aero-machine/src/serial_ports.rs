@@ -0,0 +1,117 @@
+//! Legacy COM port layout and the host byte-channel handle (see [`crate::Machine::serial_channel`]).
+//!
+//! The machine can attach up to four 16550 UARTs at the conventional PC I/O bases. COM1 is
+//! controlled by [`crate::MachineConfig::enable_serial`]; COM2-COM4 are opt-in. Ports sharing an ISA
+//! IRQ (COM1/COM3 on IRQ4 and COM2/COM4 on IRQ3 by default) are wired-OR: the line is asserted
+//! while any UART on it requests an interrupt.
+//!
+//! Guest output is drained from the UARTs when a [`crate::Machine::run_slice`] returns (and before
+//! any host serial getter runs). Each drained chunk goes to the port's [`SerialSink`]s and then to
+//! exactly one destination: the KD bridge, an attached [`SerialChannel`], or the port's output log
+//! (optionally bounded by [`crate::Machine::set_serial_log_capacity`]).
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Number of legacy COM ports (COM1..COM4). Port indices used by the machine API are `0..4`.
+pub const SERIAL_PORT_COUNT: usize = 4;
+
+/// Conventional I/O bases of COM1..COM4.
+pub const SERIAL_PORT_BASES: [u16; SERIAL_PORT_COUNT] = [0x3F8, 0x2F8, 0x3E8, 0x2E8];
+
+/// Conventional ISA IRQs of COM1..COM4 (the default for [`crate::MachineConfig::serial_irqs`]).
+pub const SERIAL_PORT_DEFAULT_IRQS: [u8; SERIAL_PORT_COUNT] = [4, 3, 4, 3];
+
+#[derive(Debug, Default)]
+struct SerialChannelQueues {
+    to_guest: VecDeque<u8>,
+    from_guest: Vec<u8>,
+}
+
+/// Host end of a bidirectional byte channel attached to a guest serial port.
+///
+/// Bytes passed to [`SerialChannel::send`] are delivered to the UART's receive FIFO at the start of
+/// the next [`crate::Machine::run_slice`]; bytes the guest transmits are queued for
+/// [`SerialChannel::recv`] instead of the port's output log. Handles are cheap clones of the same
+/// channel and stay attached across [`crate::Machine::reset`] and snapshot restore; queued bytes
+/// are host-side state and are not snapshotted.
+#[derive(Debug, Clone, Default)]
+pub struct SerialChannel {
+    queues: Rc<RefCell<SerialChannelQueues>>,
+}
+
+impl SerialChannel {
+    /// Queue bytes for delivery to the guest.
+    pub fn send(&self, bytes: &[u8]) {
+        self.queues.borrow_mut().to_guest.extend(bytes);
+    }
+
+    /// Drain the bytes the guest has transmitted so far.
+    pub fn recv(&self) -> Vec<u8> {
+        std::mem::take(&mut self.queues.borrow_mut().from_guest)
+    }
+
+    /// Number of guest-transmitted bytes waiting for [`SerialChannel::recv`].
+    pub fn recv_len(&self) -> usize {
+        self.queues.borrow().from_guest.len()
+    }
+
+    pub(crate) fn take_to_guest(&self) -> VecDeque<u8> {
+        std::mem::take(&mut self.queues.borrow_mut().to_guest)
+    }
+
+    pub(crate) fn push_from_guest(&self, bytes: &[u8]) {
+        self.queues.borrow_mut().from_guest.extend_from_slice(bytes);
+    }
+}
+
+/// Handle identifying a sink added with [`crate::Machine::add_serial_sink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SerialSinkId(u64);
+
+/// Host callback receiving the bytes a guest transmits on a COM port.
+pub type SerialSink = Box<dyn FnMut(&[u8])>;
+
+/// Serial output sinks, in the order they were added.
+#[derive(Default)]
+pub(crate) struct SerialSinks {
+    next_id: u64,
+    sinks: Vec<(SerialSinkId, usize, SerialSink)>,
+}
+
+impl SerialSinks {
+    pub(crate) fn add(&mut self, port: usize, sink: SerialSink) -> SerialSinkId {
+        let id = SerialSinkId(self.next_id);
+        self.next_id += 1;
+        self.sinks.push((id, port, sink));
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: SerialSinkId) -> bool {
+        let before = self.sinks.len();
+        self.sinks.retain(|(sink_id, _, _)| *sink_id != id);
+        self.sinks.len() != before
+    }
+
+    pub(crate) fn dispatch(&mut self, port: usize, bytes: &[u8]) {
+        for (_, sink_port, sink) in &mut self.sinks {
+            if *sink_port == port {
+                sink(bytes);
+            }
+        }
+    }
+}
+
+/// Append `bytes` to a port's output log, dropping the oldest bytes beyond `capacity`.
+pub(crate) fn append_to_log(log: &mut Vec<u8>, capacity: Option<usize>, bytes: &[u8]) {
+    log.extend_from_slice(bytes);
+    truncate_log(log, capacity);
+}
+
+/// Drop the oldest bytes of a port's output log beyond `capacity`.
+pub(crate) fn truncate_log(log: &mut Vec<u8>, capacity: Option<usize>) {
+    if let Some(excess) = capacity.and_then(|capacity| log.len().checked_sub(capacity)) {
+        log.drain(..excess);
+    }
+}
@@ -5,7 +5,7 @@ use std::rc::Rc;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::{Arc, Mutex};
 
-use aero_storage::{MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
+use aero_storage::{AllocatedRange, MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
 use firmware::bios::{BlockDevice, DiskError as BiosDiskError};
 
 use crate::MachineError;
@@ -98,8 +98,7 @@ impl SharedDisk {
             bytes.resize(SECTOR_SIZE, 0);
         }
 
-        let disk = RawDisk::open(MemBackend::from_vec(bytes))
-            .map_err(|e| MachineError::DiskBackend(e.to_string()))?;
+        let disk = RawDisk::open(MemBackend::from_vec(bytes))?;
         Ok(Box::new(disk))
     }
 }
@@ -134,12 +133,37 @@ impl VirtualDisk for SharedDisk {
             .flush()
     }
 
+    fn write_barrier(&mut self) -> aero_storage::Result<()> {
+        self.inner
+            .try_borrow_mut()
+            .expect("shared disk refcell should not already be borrowed")
+            .write_barrier()
+    }
+
     fn discard_range(&mut self, offset: u64, len: u64) -> aero_storage::Result<()> {
         self.inner
             .try_borrow_mut()
             .expect("shared disk refcell should not already be borrowed")
             .discard_range(offset, len)
     }
+
+    fn resize(&mut self, new_capacity: u64) -> aero_storage::Result<()> {
+        self.inner
+            .try_borrow_mut()
+            .expect("shared disk refcell should not already be borrowed")
+            .resize(new_capacity)
+    }
+
+    fn allocation_status(
+        &mut self,
+        offset: u64,
+        len: u64,
+    ) -> aero_storage::Result<Vec<AllocatedRange>> {
+        self.inner
+            .try_borrow_mut()
+            .expect("shared disk refcell should not already be borrowed")
+            .allocation_status(offset, len)
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -172,12 +196,37 @@ impl VirtualDisk for SharedDisk {
             .flush()
     }
 
+    fn write_barrier(&mut self) -> aero_storage::Result<()> {
+        self.inner
+            .lock()
+            .expect("shared disk mutex should not be poisoned")
+            .write_barrier()
+    }
+
     fn discard_range(&mut self, offset: u64, len: u64) -> aero_storage::Result<()> {
         self.inner
             .lock()
             .expect("shared disk mutex should not be poisoned")
             .discard_range(offset, len)
     }
+
+    fn resize(&mut self, new_capacity: u64) -> aero_storage::Result<()> {
+        self.inner
+            .lock()
+            .expect("shared disk mutex should not be poisoned")
+            .resize(new_capacity)
+    }
+
+    fn allocation_status(
+        &mut self,
+        offset: u64,
+        len: u64,
+    ) -> aero_storage::Result<Vec<AllocatedRange>> {
+        self.inner
+            .lock()
+            .expect("shared disk mutex should not be poisoned")
+            .allocation_status(offset, len)
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -230,6 +279,7 @@ mod tests {
             AeroSparseConfig {
                 disk_size_bytes: 8192,
                 block_size_bytes: 4096,
+                ..Default::default()
             },
         )
         .unwrap();
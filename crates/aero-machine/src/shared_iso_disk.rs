@@ -20,6 +20,9 @@ type SharedIsoDiskBackend = Box<dyn VirtualDisk>;
 /// same underlying ISO image:
 /// - the IDE/ATAPI CD-ROM device model (`aero_devices_storage::atapi::AtapiCdrom`)
 /// - firmware BIOS boot code (El Torito) / INT dispatch
+///
+/// Both views address the image with 64-bit sector numbers and byte offsets, so ISOs larger than
+/// 4 GiB are read without truncation. The reported capacity is always the backend size.
 #[derive(Clone)]
 pub struct SharedIsoDisk {
     #[cfg(target_arch = "wasm32")]
@@ -27,7 +30,7 @@ pub struct SharedIsoDisk {
     #[cfg(not(target_arch = "wasm32"))]
     inner: Arc<Mutex<SharedIsoDiskBackend>>,
     capacity_bytes: u64,
-    sector_count: u32,
+    sector_count: u64,
 }
 
 /// Weak reference to a [`SharedIsoDisk`] backend.
@@ -42,7 +45,7 @@ pub(crate) struct SharedIsoDiskWeak {
     #[cfg(not(target_arch = "wasm32"))]
     inner: Weak<Mutex<SharedIsoDiskBackend>>,
     capacity_bytes: u64,
-    sector_count: u32,
+    sector_count: u64,
 }
 
 impl SharedIsoDisk {
@@ -56,12 +59,6 @@ impl SharedIsoDisk {
         }
 
         let sector_count = capacity_bytes / AtapiCdrom::SECTOR_SIZE as u64;
-        let sector_count = u32::try_from(sector_count).map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "ISO disk capacity exceeds 32-bit sector count limit",
-            )
-        })?;
 
         #[cfg(target_arch = "wasm32")]
         let inner = Rc::new(RefCell::new(disk));
@@ -137,11 +134,11 @@ impl VirtualDisk for SharedIsoDisk {
 }
 
 impl IsoBackend for SharedIsoDisk {
-    fn sector_count(&self) -> u32 {
+    fn sector_count(&self) -> u64 {
         self.sector_count
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         if !buf.len().is_multiple_of(AtapiCdrom::SECTOR_SIZE) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -149,7 +146,7 @@ impl IsoBackend for SharedIsoDisk {
             ));
         }
 
-        let offset = u64::from(lba)
+        let offset = lba
             .checked_mul(AtapiCdrom::SECTOR_SIZE as u64)
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "offset overflow"))?;
 
@@ -186,7 +183,7 @@ impl CdromDevice for SharedIsoDisk {
     }
 
     fn size_in_sectors(&self) -> u64 {
-        u64::from(self.sector_count)
+        self.sector_count
     }
 }
 
@@ -0,0 +1,227 @@
+//! Save/restore determinism probes (see [`crate::Machine::run_slice_with_snapshot_probe`]).
+//!
+//! A probe run splits a slice into fixed-size chunks and, at each chunk boundary, takes a full
+//! snapshot together with a [`StateFingerprint`] of the guest-visible machine state. Restoring a
+//! probe's snapshot into a fresh machine and running the same chunks there must reproduce the
+//! fingerprints of every later probe; [`StateFingerprint::diff`] names the section (RAM page,
+//! vCPU, device entry and TLV field, or serial output) where the two runs first disagree.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::Hasher;
+
+use aero_io_snapshot::io::state::SnapshotReader;
+use aero_snapshot as snapshot;
+
+use crate::device_snapshot;
+use crate::RunExit;
+
+/// Granularity of [`StateFingerprint::ram_pages`].
+pub const FINGERPRINT_PAGE_SIZE: usize = 4096;
+
+/// Comparable summary of the guest-visible state of a machine at an instruction boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateFingerprint {
+    /// Hash of each [`FINGERPRINT_PAGE_SIZE`] page of guest RAM, indexed by dense RAM offset (as
+    /// in snapshots, i.e. not including MMIO holes).
+    pub ram_pages: Vec<u64>,
+    /// vCPU state as it would be snapshotted, sorted by APIC ID.
+    pub cpus: Vec<snapshot::VcpuSnapshot>,
+    /// Device entries as they would be snapshotted, sorted by id.
+    pub devices: Vec<snapshot::DeviceState>,
+    /// COM1 output produced since the previous probe of the same run.
+    pub serial: Vec<u8>,
+}
+
+/// One section in which two [`StateFingerprint`]s differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDivergence {
+    /// Guest RAM differs in `pages` pages, the first at dense RAM offset `first_offset`.
+    Ram { first_offset: u64, pages: usize },
+    /// The vCPU with this APIC ID differs (or exists on one side only).
+    Cpu { apic_id: u32 },
+    /// A device entry differs (or exists on one side only).
+    Device {
+        id: snapshot::DeviceId,
+        /// 4CC of the entry's `aero-io-snapshot` payload, for devices that use that encoding.
+        io_device_id: Option<[u8; 4]>,
+        /// TLV tags whose contents differ. Empty when the payload is not an `aero-io-snapshot`
+        /// blob or differs in its header.
+        fields: Vec<u16>,
+    },
+    /// Serial output differs after `common_prefix` matching bytes.
+    Serial { common_prefix: usize },
+}
+
+impl fmt::Display for StateDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateDivergence::Ram {
+                first_offset,
+                pages,
+            } => write!(f, "RAM: {pages} page(s) differ, first at {first_offset:#x}"),
+            StateDivergence::Cpu { apic_id } => write!(f, "vCPU (APIC ID {apic_id})"),
+            StateDivergence::Device {
+                id,
+                io_device_id,
+                fields,
+            } => {
+                match id.name() {
+                    Some(name) => write!(f, "device {name}")?,
+                    None => write!(f, "device {id:?}")?,
+                }
+                if let Some(fourcc) = io_device_id {
+                    write!(f, " [{}]", String::from_utf8_lossy(fourcc))?;
+                }
+                if !fields.is_empty() {
+                    write!(f, " fields {fields:?}")?;
+                }
+                Ok(())
+            }
+            StateDivergence::Serial { common_prefix } => {
+                write!(f, "serial output after {common_prefix} byte(s)")
+            }
+        }
+    }
+}
+
+impl StateFingerprint {
+    pub(crate) fn capture(
+        source: &impl snapshot::SnapshotSource,
+        serial: Vec<u8>,
+    ) -> snapshot::Result<Self> {
+        let mut ram_pages = Vec::with_capacity(source.ram_len().div_ceil(FINGERPRINT_PAGE_SIZE));
+        let mut page = vec![0u8; FINGERPRINT_PAGE_SIZE];
+        let mut offset = 0usize;
+        while offset < source.ram_len() {
+            let len = FINGERPRINT_PAGE_SIZE.min(source.ram_len() - offset);
+            source.read_ram(offset as u64, &mut page[..len])?;
+            let mut hasher = DefaultHasher::new();
+            hasher.write(&page[..len]);
+            ram_pages.push(hasher.finish());
+            offset += len;
+        }
+
+        let mut cpus = source.cpu_states();
+        cpus.sort_by_key(|cpu| cpu.apic_id);
+        let mut devices = source.device_states();
+        devices.sort_by_key(|state| (state.id.0, state.version, state.flags));
+
+        Ok(Self {
+            ram_pages,
+            cpus,
+            devices,
+            serial,
+        })
+    }
+
+    /// List the sections in which `other` differs from `self` (empty if the fingerprints match).
+    pub fn diff(&self, other: &Self) -> Vec<StateDivergence> {
+        let mut out = Vec::new();
+
+        let differing_pages: Vec<usize> = (0..self.ram_pages.len().max(other.ram_pages.len()))
+            .filter(|&i| self.ram_pages.get(i) != other.ram_pages.get(i))
+            .collect();
+        if let Some(&first) = differing_pages.first() {
+            out.push(StateDivergence::Ram {
+                first_offset: (first * FINGERPRINT_PAGE_SIZE) as u64,
+                pages: differing_pages.len(),
+            });
+        }
+
+        let mut apic_ids: Vec<u32> = self
+            .cpus
+            .iter()
+            .chain(&other.cpus)
+            .map(|cpu| cpu.apic_id)
+            .collect();
+        apic_ids.sort_unstable();
+        apic_ids.dedup();
+        for apic_id in apic_ids {
+            let ours = self.cpus.iter().find(|cpu| cpu.apic_id == apic_id);
+            let theirs = other.cpus.iter().find(|cpu| cpu.apic_id == apic_id);
+            if ours != theirs {
+                out.push(StateDivergence::Cpu { apic_id });
+            }
+        }
+
+        // Ids that hold several entries (disk controllers) are paired by their io-snapshot 4CC.
+        let key =
+            |state: &snapshot::DeviceState| (state.id, device_snapshot::io_device_id(&state.data));
+        let mut unmatched: Vec<&snapshot::DeviceState> = other.devices.iter().collect();
+        for ours in &self.devices {
+            let theirs = unmatched
+                .iter()
+                .position(|theirs| key(theirs) == key(ours))
+                .map(|idx| unmatched.remove(idx));
+            if theirs != Some(ours) {
+                out.push(StateDivergence::Device {
+                    id: ours.id,
+                    io_device_id: key(ours).1,
+                    fields: theirs.map_or_else(Vec::new, |theirs| differing_fields(ours, theirs)),
+                });
+            }
+        }
+        for theirs in unmatched {
+            out.push(StateDivergence::Device {
+                id: theirs.id,
+                io_device_id: key(theirs).1,
+                fields: Vec::new(),
+            });
+        }
+
+        if self.serial != other.serial {
+            let common_prefix = self
+                .serial
+                .iter()
+                .zip(&other.serial)
+                .take_while(|(a, b)| a == b)
+                .count();
+            out.push(StateDivergence::Serial { common_prefix });
+        }
+
+        out
+    }
+}
+
+/// TLV tags that differ between two entries carrying the same `aero-io-snapshot` device.
+fn differing_fields(a: &snapshot::DeviceState, b: &snapshot::DeviceState) -> Vec<u16> {
+    let Some(fourcc) = device_snapshot::io_device_id(&a.data) else {
+        return Vec::new();
+    };
+    let (Ok(a), Ok(b)) = (
+        SnapshotReader::parse(&a.data, fourcc),
+        SnapshotReader::parse(&b.data, fourcc),
+    ) else {
+        return Vec::new();
+    };
+    let mut tags: Vec<u16> = a
+        .iter_fields()
+        .chain(b.iter_fields())
+        .map(|(tag, _)| tag)
+        .filter(|&tag| a.bytes(tag) != b.bytes(tag))
+        .collect();
+    tags.sort_unstable();
+    tags.dedup();
+    tags
+}
+
+/// A chunk boundary of [`crate::Machine::run_slice_with_snapshot_probe`].
+#[derive(Debug, Clone)]
+pub struct SnapshotProbe {
+    /// Instructions executed by the probe run before this boundary.
+    pub executed: u64,
+    /// Full snapshot taken at the boundary.
+    pub snapshot: Vec<u8>,
+    /// Guest-visible state at the boundary (after the snapshot was taken).
+    pub fingerprint: StateFingerprint,
+}
+
+/// Result of [`crate::Machine::run_slice_with_snapshot_probe`].
+#[derive(Debug, Clone)]
+pub struct SnapshotProbeRun {
+    /// How the run ended; `executed` covers the whole run.
+    pub exit: RunExit,
+    /// One probe per chunk, in execution order.
+    pub probes: Vec<SnapshotProbe>,
+}
@@ -0,0 +1,148 @@
+//! Structured export of the legacy text-mode screen (see [`crate::Machine::read_text_screen`]).
+//!
+//! Cells are decoded from the same state the text renderers draw: the VGA device's VRAM and CRTC
+//! registers when it owns the display, otherwise the `0xB8000` buffer at the BIOS Data Area's
+//! active page (see [`crate::aerogpu_legacy_text`]).
+
+use firmware::bda::BiosDataArea;
+use memory::MemoryBus;
+
+// Defensive caps on guest-controlled BDA geometry; large enough for the VESA 132x60 text mode.
+const MAX_TEXT_COLS: u16 = 132;
+const MAX_TEXT_ROWS: u8 = 60;
+
+/// Attribute Controller Mode Control bit 3: attribute bit 7 selects blink instead of a bright
+/// background.
+const MODE_CONTROL_BLINK: u8 = 1 << 3;
+
+/// One character cell of a [`TextScreen`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextCell {
+    /// The CP437 character code decoded to Unicode.
+    pub ch: char,
+    /// Foreground attribute colour index (0-15).
+    pub fg: u8,
+    /// Background attribute colour index (0-15, or 0-7 when blink is enabled).
+    pub bg: u8,
+    /// The cell blinks (attribute bit 7 while blink is enabled).
+    pub blink: bool,
+}
+
+/// Snapshot of the visible legacy text page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextScreen {
+    pub cols: usize,
+    pub rows: usize,
+    /// Row-major cells, `cols * rows` long.
+    pub cells: Vec<TextCell>,
+    /// Cursor `(row, col)`. It may lie outside the grid (e.g. when the guest parks it off-screen).
+    pub cursor: (u8, u8),
+}
+
+impl TextScreen {
+    /// The cell at `row`/`col`, or `None` outside the grid.
+    pub fn cell(&self, row: usize, col: usize) -> Option<&TextCell> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        self.cells.get(row * self.cols + col)
+    }
+
+    /// The characters of `row` with trailing spaces removed (empty outside the grid).
+    pub fn row_text(&self, row: usize) -> String {
+        if row >= self.rows {
+            return String::new();
+        }
+        let start = row * self.cols;
+        let line: String = self.cells[start..start + self.cols]
+            .iter()
+            .map(|cell| cell.ch)
+            .collect();
+        line.trim_end_matches(' ').to_string()
+    }
+
+    /// Every row's [`TextScreen::row_text`], joined with `\n`.
+    pub fn text(&self) -> String {
+        (0..self.rows)
+            .map(|row| self.row_text(row))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub(crate) fn from_cells(
+        cols: usize,
+        rows: usize,
+        cells: impl IntoIterator<Item = (u8, u8)>,
+        cursor: (u8, u8),
+        mode_control: u8,
+    ) -> Self {
+        let blink_enabled = mode_control & MODE_CONTROL_BLINK != 0;
+        let cells = cells
+            .into_iter()
+            .map(|(ch, attr)| TextCell {
+                ch: cp437_to_char(ch),
+                fg: attr & 0x0F,
+                bg: if blink_enabled {
+                    (attr >> 4) & 0x07
+                } else {
+                    attr >> 4
+                },
+                blink: blink_enabled && attr & 0x80 != 0,
+            })
+            .collect();
+        Self {
+            cols,
+            rows,
+            cells,
+            cursor,
+        }
+    }
+}
+
+/// Read the active text page from `0xB8000` using BDA geometry, page and cursor state, like
+/// [`crate::aerogpu_legacy_text::render_into`].
+pub(crate) fn read_bda(mem: &mut impl MemoryBus, mode_control: u8) -> TextScreen {
+    let cols = BiosDataArea::read_screen_cols(mem).clamp(1, MAX_TEXT_COLS) as usize;
+    let rows = BiosDataArea::read_text_rows(mem).clamp(1, MAX_TEXT_ROWS) as usize;
+    let base = 0xB8000u64 + u64::from(BiosDataArea::read_video_page_offset(mem));
+    let page = BiosDataArea::read_active_page(mem);
+    let cursor = BiosDataArea::read_cursor_pos(mem, page);
+
+    let mut buf = vec![0u8; cols * rows * 2];
+    mem.read_physical(base, &mut buf);
+    TextScreen::from_cells(
+        cols,
+        rows,
+        buf.chunks_exact(2).map(|cell| (cell[0], cell[1])),
+        cursor,
+        mode_control,
+    )
+}
+
+/// Unicode equivalents of CP437 0x00-0x1F (the glyphs the VGA font draws; NUL is blank).
+const CP437_CONTROL: [char; 32] = [
+    ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', //
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+/// Unicode equivalents of CP437 0x80-0xFF (0xFF, a non-breaking space, maps to a plain space).
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', ' ',
+];
+
+/// Decode a CP437 character code to the Unicode character its VGA glyph depicts.
+pub fn cp437_to_char(byte: u8) -> char {
+    match byte {
+        0x00..=0x1F => CP437_CONTROL[usize::from(byte)],
+        0x20..=0x7E => char::from(byte),
+        0x7F => '⌂',
+        0x80..=0xFF => CP437_HIGH[usize::from(byte - 0x80)],
+    }
+}
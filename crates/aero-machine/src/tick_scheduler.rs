@@ -0,0 +1,160 @@
+//! Device tick scheduling (see [`crate::Machine::set_device_tick_scheduling`]).
+//!
+//! Without scheduling, [`crate::Machine::tick_platform`] and [`crate::Machine::poll_network`] visit
+//! every device on every step. With it, devices are only visited when they may have work:
+//!
+//! - Devices that can cheaply tell that a tick would be a no-op (halted USB controllers with quiet
+//!   root ports, an E1000 with no host backend and nothing queued, AeroGPU with scanout disabled)
+//!   are asked every step and skipped while idle.
+//! - The HPET is parked until the guest time of its next comparator match (kept in a small deadline
+//!   heap) or until the guest writes its MMIO window, which acts as a doorbell.
+//!
+//! A skipped step is an exact no-op for the device, so interrupt timing does not change. After a
+//! reset, a snapshot restore or a scheduling toggle every device is visited once before it can be
+//! skipped again.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Devices whose ticks the scheduler may skip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum TickDevice {
+    Hpet,
+    Uhci,
+    Ehci,
+    AeroGpu,
+    Network,
+}
+
+impl TickDevice {
+    const COUNT: usize = 5;
+    const ALL_BITS: u8 = (1 << Self::COUNT) - 1;
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Why a parked device sleeps: until guest time reaches `deadline_ns`, or until its doorbell count
+/// moves away from `doorbell`.
+#[derive(Debug, Clone, Copy)]
+struct Parked {
+    deadline_ns: Option<u64>,
+    doorbell: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct TickScheduler {
+    enabled: bool,
+    /// Devices that must be visited on their next step whatever their idle state.
+    woken: u8,
+    parked: [Option<Parked>; TickDevice::COUNT],
+    /// Deadlines of parked devices, earliest first. Entries left behind by a device that was woken
+    /// early are dropped when they surface.
+    deadlines: BinaryHeap<Reverse<(u64, TickDevice)>>,
+}
+
+impl Default for TickScheduler {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            woken: TickDevice::ALL_BITS,
+            parked: [None; TickDevice::COUNT],
+            deadlines: BinaryHeap::new(),
+        }
+    }
+}
+
+impl TickScheduler {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.wake_all();
+    }
+
+    /// Forget everything learned about device state, e.g. after a reset or snapshot restore.
+    pub(crate) fn wake_all(&mut self) {
+        self.woken = TickDevice::ALL_BITS;
+        self.parked = [None; TickDevice::COUNT];
+        self.deadlines.clear();
+    }
+
+    /// Whether this step of `device` can be skipped, given whether the device reports itself idle.
+    pub(crate) fn skip_idle(&mut self, device: TickDevice, idle: bool) -> bool {
+        let woken = self.woken & device.bit() != 0;
+        self.woken &= !device.bit();
+        self.enabled && idle && !woken
+    }
+
+    pub(crate) fn is_parked(&self, device: TickDevice) -> bool {
+        self.parked[device as usize].is_some()
+    }
+
+    /// Whether parked `device` must be visited at guest time `now_ns`, given its current doorbell
+    /// count. A device that is due is unparked.
+    pub(crate) fn parked_due(&mut self, device: TickDevice, now_ns: u64, doorbell: u64) -> bool {
+        let slot = device as usize;
+        if !self.enabled || self.woken & device.bit() != 0 {
+            self.woken &= !device.bit();
+            self.parked[slot] = None;
+            return true;
+        }
+
+        while let Some(&Reverse((deadline_ns, due))) = self.deadlines.peek() {
+            if deadline_ns > now_ns {
+                break;
+            }
+            self.deadlines.pop();
+            let parked = &mut self.parked[due as usize];
+            if parked.is_some_and(|parked| parked.deadline_ns == Some(deadline_ns)) {
+                *parked = None;
+            }
+        }
+
+        match self.parked[slot] {
+            Some(parked) if parked.doorbell == doorbell => false,
+            _ => {
+                self.parked[slot] = None;
+                true
+            }
+        }
+    }
+
+    /// Park `device` after it was visited, until guest time reaches `deadline_ns` (if any) or its
+    /// doorbell count moves away from `doorbell`.
+    pub(crate) fn park(&mut self, device: TickDevice, deadline_ns: Option<u64>, doorbell: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.parked[device as usize] = Some(Parked {
+            deadline_ns,
+            doorbell,
+        });
+        if let Some(deadline_ns) = deadline_ns {
+            // A guest that keeps reprogramming a far-off comparator leaves stale entries behind;
+            // keep the heap bounded by the live deadlines.
+            if self.deadlines.len() >= 4 * TickDevice::COUNT {
+                let parked = &self.parked;
+                self.deadlines.retain(|&Reverse((deadline_ns, device))| {
+                    parked[device as usize]
+                        .is_some_and(|parked| parked.deadline_ns == Some(deadline_ns))
+                });
+            }
+            self.deadlines.push(Reverse((deadline_ns, device)));
+        }
+    }
+}
+
+/// Advance a per-millisecond tick remainder by `delta_ns` without ticking, returning the number of
+/// whole milliseconds skipped.
+pub(crate) fn skip_ms_ticks(remainder_ns: &mut u64, delta_ns: u64) -> u64 {
+    const NS_PER_MS: u64 = 1_000_000;
+
+    *remainder_ns = remainder_ns.saturating_add(delta_ns);
+    let ticks = *remainder_ns / NS_PER_MS;
+    *remainder_ns %= NS_PER_MS;
+    ticks
+}
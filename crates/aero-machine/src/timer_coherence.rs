@@ -0,0 +1,342 @@
+//! Timekeeping cross-calibration self-test (see [`crate::Machine::verify_timer_coherence`]).
+//!
+//! Guests calibrate their clocks against each other at boot: Windows measures the TSC against the
+//! ACPI PM timer and the HPET, and demotes (or distrusts) a clocksource whose implied frequency
+//! disagrees. Every source here is driven from the same guest nanosecond stream, so they should
+//! agree to within rounding; a source that drifts means its ns → tick conversion has diverged.
+//!
+//! The self-test advances guest time through the same cycle-driven path `run_slice` uses while
+//! the CPU is halted, counts each source's ticks the way a guest observes them (PM timer and HPET
+//! through their registers, including 24-bit PM timer wrap-around), and compares the implied
+//! frequency against the nominal one.
+//!
+//! Counting whole ticks over a finite interval has an inherent error of up to one tick, which for
+//! slow sources (the RTC periodic interrupt) dwarfs any realistic ppm threshold. Each sample
+//! therefore carries its `resolution_ppm` and is only flagged when the deviation exceeds the
+//! configured tolerance *plus* that resolution.
+
+use aero_devices::acpi_pm::PM_TIMER_HZ;
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_devices::pit8254::PIT_HZ;
+
+use crate::Machine;
+
+/// Default [`TimerCoherenceConfig::tolerance_ppm`].
+pub const DEFAULT_TIMER_COHERENCE_TOLERANCE_PPM: u32 = 50;
+
+/// Default [`TimerCoherenceConfig::step_ns`] (the halted-CPU tick of `run_slice`).
+pub const DEFAULT_TIMER_COHERENCE_STEP_NS: u64 = 1_000_000;
+
+const HPET_REG_CAPABILITIES: u64 = 0x00;
+const HPET_REG_GENERAL_CONFIG: u64 = 0x10;
+const HPET_REG_MAIN_COUNTER: u64 = 0xF0;
+const HPET_GENERAL_CONFIG_ENABLE: u64 = 1 << 0;
+
+const PM_TIMER_MASK: u32 = 0x00FF_FFFF;
+
+/// Settings for [`crate::Machine::verify_timer_coherence_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerCoherenceConfig {
+    /// Largest accepted deviation from a source's nominal frequency, in parts per million, on top
+    /// of the source's counting resolution.
+    pub tolerance_ppm: u32,
+    /// Guest time advanced per platform tick. Smaller steps exercise the remainder carry of each
+    /// device's conversion more often.
+    ///
+    /// Steps are rounded to whole CPU cycles (at least one). Keep them well below the 24-bit PM
+    /// timer wrap period (~4.7s) so wrap-around can be unfolded.
+    pub step_ns: u64,
+}
+
+impl Default for TimerCoherenceConfig {
+    fn default() -> Self {
+        Self {
+            tolerance_ppm: DEFAULT_TIMER_COHERENCE_TOLERANCE_PPM,
+            step_ns: DEFAULT_TIMER_COHERENCE_STEP_NS,
+        }
+    }
+}
+
+/// A timekeeping source checked by the self-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerSource {
+    /// PIT 8254 input clock.
+    Pit,
+    /// ACPI PM timer (`PM_TMR`).
+    PmTimer,
+    /// HPET main counter.
+    Hpet,
+    /// Time stamp counter of the vCPU with this index (0 is the BSP).
+    Tsc(usize),
+    /// RTC periodic interrupt at the rate selected in register A.
+    RtcPeriodic,
+}
+
+/// Measured behaviour of one [`TimerSource`] over the self-test interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimerSourceSample {
+    pub source: TimerSource,
+    /// Frequency the guest is told (or assumes) the source runs at.
+    pub nominal_hz: f64,
+    /// Ticks counted over the interval.
+    pub ticks: u64,
+    /// `ticks` divided by the elapsed guest time.
+    pub implied_hz: f64,
+    /// Signed deviation of `implied_hz` from `nominal_hz`, in parts per million.
+    pub deviation_ppm: f64,
+    /// Deviation one tick of counting error accounts for over this interval.
+    pub resolution_ppm: f64,
+    /// Whether `|deviation_ppm|` is within the configured tolerance plus `resolution_ppm`.
+    pub within_tolerance: bool,
+}
+
+impl TimerSourceSample {
+    fn new(
+        source: TimerSource,
+        nominal_hz: f64,
+        ticks: u64,
+        elapsed_secs: f64,
+        tolerance_ppm: u32,
+    ) -> Self {
+        let expected_ticks = nominal_hz * elapsed_secs;
+        let implied_hz = if elapsed_secs > 0.0 {
+            ticks as f64 / elapsed_secs
+        } else {
+            0.0
+        };
+        let deviation_ppm = if nominal_hz > 0.0 {
+            (implied_hz - nominal_hz) / nominal_hz * 1e6
+        } else {
+            0.0
+        };
+        let resolution_ppm = if expected_ticks > 0.0 {
+            1e6 / expected_ticks
+        } else {
+            f64::INFINITY
+        };
+        Self {
+            source,
+            nominal_hz,
+            ticks,
+            implied_hz,
+            deviation_ppm,
+            resolution_ppm,
+            within_tolerance: deviation_ppm.abs() <= f64::from(tolerance_ppm) + resolution_ppm,
+        }
+    }
+}
+
+/// Result of [`crate::Machine::verify_timer_coherence`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimerCoherenceReport {
+    /// Guest time the self-test advanced, derived from the CPU cycles it retired.
+    pub elapsed_ns: u64,
+    /// Platform tick size actually used (the configured step rounded to whole cycles).
+    pub step_ns: u64,
+    /// Configured tolerance.
+    pub tolerance_ppm: u32,
+    /// One entry per running source.
+    pub samples: Vec<TimerSourceSample>,
+    /// Sources present on the machine that were not counting: the HPET before the guest sets
+    /// `ENABLE_CNF`, or the RTC with periodic interrupts disabled.
+    pub not_running: Vec<TimerSource>,
+}
+
+impl TimerCoherenceReport {
+    /// `true` when every running source agrees with its nominal frequency.
+    pub fn is_coherent(&self) -> bool {
+        self.samples.iter().all(|sample| sample.within_tolerance)
+    }
+
+    /// Samples that deviate beyond the tolerance.
+    pub fn outliers(&self) -> impl Iterator<Item = &TimerSourceSample> {
+        self.samples
+            .iter()
+            .filter(|sample| !sample.within_tolerance)
+    }
+
+    /// The sample for `source`, if it was running.
+    pub fn sample(&self, source: TimerSource) -> Option<&TimerSourceSample> {
+        self.samples.iter().find(|sample| sample.source == source)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HpetProbe {
+    nominal_hz: f64,
+    start: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RtcProbe {
+    nominal_hz: u32,
+    start: u64,
+}
+
+/// Start-of-interval counter values captured by the self-test.
+pub(crate) struct TimerProbe {
+    pit_start: Option<u64>,
+    /// PM timer port and the last 24-bit value read from it.
+    pm_timer: Option<(u16, u32)>,
+    /// PM timer ticks accumulated across reads (wrap-around unfolded).
+    pm_timer_ticks: u64,
+    hpet: Option<HpetProbe>,
+    hpet_present: bool,
+    rtc: Option<RtcProbe>,
+    rtc_present: bool,
+    tsc_start: Vec<u64>,
+}
+
+impl TimerProbe {
+    pub(crate) fn start(m: &mut Machine) -> Self {
+        let pit_start = m.pit.as_ref().map(|pit| pit.borrow().elapsed_ticks());
+
+        let pm_timer = m
+            .acpi_pm
+            .as_ref()
+            .map(|pm| pm.borrow().cfg().pm_tmr_blk)
+            .map(|port| (port, m.io_read(port, 4) & PM_TIMER_MASK));
+
+        let hpet_present = m.hpet.is_some();
+        let hpet = if hpet_present { probe_hpet(m) } else { None };
+
+        let rtc_present = m.rtc.is_some();
+        let rtc = m.rtc.as_ref().and_then(|rtc| {
+            let rtc = rtc.borrow();
+            rtc.periodic_frequency_hz().map(|nominal_hz| RtcProbe {
+                nominal_hz,
+                start: rtc.periodic_ticks(),
+            })
+        });
+
+        let tsc_start = Self::read_tscs(m);
+
+        Self {
+            pit_start,
+            pm_timer,
+            pm_timer_ticks: 0,
+            hpet,
+            hpet_present,
+            rtc,
+            rtc_present,
+            tsc_start,
+        }
+    }
+
+    /// Fold the PM timer's progress since the previous read into the running tick count. Must be
+    /// called at least once per wrap period.
+    pub(crate) fn sample_pm_timer(&mut self, m: &mut Machine) {
+        if let Some((port, last)) = self.pm_timer.as_mut() {
+            let now = m.io_read(*port, 4) & PM_TIMER_MASK;
+            self.pm_timer_ticks += u64::from(now.wrapping_sub(*last) & PM_TIMER_MASK);
+            *last = now;
+        }
+    }
+
+    pub(crate) fn finish(
+        mut self,
+        m: &mut Machine,
+        elapsed_cycles: u64,
+        tsc_hz: u64,
+        cfg: &TimerCoherenceConfig,
+    ) -> TimerCoherenceReport {
+        self.sample_pm_timer(m);
+
+        let elapsed_secs = if tsc_hz == 0 {
+            0.0
+        } else {
+            elapsed_cycles as f64 / tsc_hz as f64
+        };
+        let tolerance_ppm = cfg.tolerance_ppm;
+        let sample = |source, nominal_hz, ticks| {
+            TimerSourceSample::new(source, nominal_hz, ticks, elapsed_secs, tolerance_ppm)
+        };
+
+        let mut samples = Vec::new();
+        let mut not_running = Vec::new();
+
+        if let (Some(start), Some(pit)) = (self.pit_start, m.pit.as_ref()) {
+            let ticks = pit.borrow().elapsed_ticks().wrapping_sub(start);
+            samples.push(sample(TimerSource::Pit, PIT_HZ as f64, ticks));
+        }
+        if self.pm_timer.is_some() {
+            samples.push(sample(
+                TimerSource::PmTimer,
+                PM_TIMER_HZ as f64,
+                self.pm_timer_ticks,
+            ));
+        }
+        match self.hpet {
+            Some(hpet) => {
+                let now = m.read_physical_u64(HPET_MMIO_BASE + HPET_REG_MAIN_COUNTER);
+                samples.push(sample(
+                    TimerSource::Hpet,
+                    hpet.nominal_hz,
+                    now.wrapping_sub(hpet.start),
+                ));
+            }
+            None if self.hpet_present => not_running.push(TimerSource::Hpet),
+            None => {}
+        }
+        for (cpu, (start, now)) in self.tsc_start.iter().zip(Self::read_tscs(m)).enumerate() {
+            samples.push(sample(
+                TimerSource::Tsc(cpu),
+                tsc_hz as f64,
+                now.wrapping_sub(*start),
+            ));
+        }
+        match (self.rtc, m.rtc.as_ref()) {
+            (Some(probe), Some(rtc)) => {
+                let ticks = rtc.borrow().periodic_ticks().wrapping_sub(probe.start);
+                samples.push(sample(
+                    TimerSource::RtcPeriodic,
+                    f64::from(probe.nominal_hz),
+                    ticks,
+                ));
+            }
+            _ if self.rtc_present => not_running.push(TimerSource::RtcPeriodic),
+            _ => {}
+        }
+
+        let step_ns = if tsc_hz == 0 {
+            0
+        } else {
+            (u128::from(cycles_for_ns(cfg.step_ns, tsc_hz)) * 1_000_000_000 / u128::from(tsc_hz))
+                as u64
+        };
+        TimerCoherenceReport {
+            elapsed_ns: (elapsed_secs * 1e9).round() as u64,
+            step_ns,
+            tolerance_ppm,
+            samples,
+            not_running,
+        }
+    }
+
+    fn read_tscs(m: &Machine) -> Vec<u64> {
+        std::iter::once(m.cpu.state.msr.tsc)
+            .chain(m.ap_cpus.iter().map(|cpu| cpu.state.msr.tsc))
+            .collect()
+    }
+}
+
+fn probe_hpet(m: &mut Machine) -> Option<HpetProbe> {
+    let config = m.read_physical_u64(HPET_MMIO_BASE + HPET_REG_GENERAL_CONFIG);
+    if config & HPET_GENERAL_CONFIG_ENABLE == 0 {
+        return None;
+    }
+    let period_fs = m.read_physical_u64(HPET_MMIO_BASE + HPET_REG_CAPABILITIES) >> 32;
+    if period_fs == 0 {
+        return None;
+    }
+    Some(HpetProbe {
+        nominal_hz: 1e15 / period_fs as f64,
+        start: m.read_physical_u64(HPET_MMIO_BASE + HPET_REG_MAIN_COUNTER),
+    })
+}
+
+/// CPU cycles covering `ns` of guest time at `tsc_hz` (rounded up, at least one).
+pub(crate) fn cycles_for_ns(ns: u64, tsc_hz: u64) -> u64 {
+    let cycles = (u128::from(ns) * u128::from(tsc_hz)).div_ceil(1_000_000_000);
+    u64::try_from(cycles).unwrap_or(u64::MAX).max(1)
+}
@@ -0,0 +1,180 @@
+//! Aggregated report of guest behaviour the CPU model could not service (see
+//! [`crate::Machine::take_unimplemented_report`]).
+//!
+//! The raw data is collected by the Tier-0 assist layer
+//! ([`aero_cpu_core::assist_telemetry::AssistTelemetry`]) across every vCPU and survives
+//! [`crate::Machine::reset`], so a report taken after a failed boot attempt covers all of its
+//! reboots. Entries are sorted by hit count, most frequent first.
+//!
+//! With the `serde` feature the report implements `serde::Serialize` so the browser host can
+//! attach it to bug reports as JSON. Instruction bytes serialize as space-separated hex, CPU modes
+//! and assist reasons as lowercase names, and exceptions in their `Display` form.
+
+use aero_cpu_core::assist_telemetry::AssistTelemetry;
+use aero_cpu_core::state::CpuMode;
+use aero_cpu_core::{AssistReason, Exception};
+
+/// An instruction the interpreter failed on with `#UD` or [`Exception::Unimplemented`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnimplementedInstruction {
+    /// RIP of the first occurrence.
+    pub rip: u64,
+    /// The 15 bytes fetched at `rip`.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_hex_bytes"))]
+    pub bytes: Vec<u8>,
+    /// Length of the decoded instruction within `bytes` (15 if it did not decode). Entries are
+    /// deduplicated on these leading bytes.
+    pub len: u8,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_cpu_mode"))]
+    pub mode: CpuMode,
+    /// Effective code size (16/32/64).
+    pub bitness: u32,
+    /// Lowercase mnemonic, if the decoder recognised the instruction.
+    pub mnemonic: Option<String>,
+    /// Assist class the instruction was routed through, if any.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_assist_reason"))]
+    pub assist: Option<AssistReason>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_exception"))]
+    pub exception: Exception,
+    pub hits: u64,
+}
+
+/// An `RDMSR`/`WRMSR` the CPU model refused with `#GP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RefusedMsrAccess {
+    pub msr: u32,
+    pub write: bool,
+    /// Value of the most recent refused write.
+    pub last_value: Option<u64>,
+    /// RIP of the first occurrence.
+    pub rip: u64,
+    pub hits: u64,
+}
+
+/// A `CPUID` leaf that returned all-zero registers and was queried more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZeroCpuidLeaf {
+    pub leaf: u32,
+    pub subleaf: u32,
+    pub hits: u64,
+}
+
+/// Everything collected since the last [`crate::Machine::take_unimplemented_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnimplementedReport {
+    pub instructions: Vec<UnimplementedInstruction>,
+    pub refused_msrs: Vec<RefusedMsrAccess>,
+    /// Zero `CPUID` leaves the guest retried. Leaves probed only once are not reported.
+    pub zero_cpuid_leaves: Vec<ZeroCpuidLeaf>,
+    /// New entries dropped because a table reached
+    /// [`aero_cpu_core::assist_telemetry::ASSIST_TELEMETRY_CAP`].
+    pub dropped: u64,
+}
+
+impl UnimplementedReport {
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+            && self.refused_msrs.is_empty()
+            && self.zero_cpuid_leaves.is_empty()
+            && self.dropped == 0
+    }
+}
+
+pub(crate) fn build(telemetry: AssistTelemetry) -> UnimplementedReport {
+    let mut instructions: Vec<_> = telemetry
+        .unimplemented
+        .into_iter()
+        .map(|site| UnimplementedInstruction {
+            rip: site.rip,
+            bytes: site.bytes.to_vec(),
+            len: site.len,
+            mode: site.mode,
+            bitness: site.bitness,
+            mnemonic: site
+                .mnemonic
+                .map(|mnemonic| format!("{mnemonic:?}").to_ascii_lowercase()),
+            assist: site.assist,
+            exception: site.exception,
+            hits: site.hits,
+        })
+        .collect();
+    let mut refused_msrs: Vec<_> = telemetry
+        .refused_msrs
+        .into_iter()
+        .map(|entry| RefusedMsrAccess {
+            msr: entry.msr,
+            write: entry.write,
+            last_value: entry.last_value,
+            rip: entry.rip,
+            hits: entry.hits,
+        })
+        .collect();
+    let mut zero_cpuid_leaves: Vec<_> = telemetry
+        .zero_cpuid_leaves
+        .into_iter()
+        .filter(|entry| entry.hits > 1)
+        .map(|entry| ZeroCpuidLeaf {
+            leaf: entry.leaf,
+            subleaf: entry.subleaf,
+            hits: entry.hits,
+        })
+        .collect();
+
+    // Stable sorts: ties keep first-seen order.
+    instructions.sort_by(|a, b| b.hits.cmp(&a.hits));
+    refused_msrs.sort_by(|a, b| b.hits.cmp(&a.hits));
+    zero_cpuid_leaves.sort_by(|a, b| b.hits.cmp(&a.hits));
+
+    UnimplementedReport {
+        instructions,
+        refused_msrs,
+        zero_cpuid_leaves,
+        dropped: telemetry.dropped,
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serialize_hex_bytes<S: serde::Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    s.serialize_str(&hex.join(" "))
+}
+
+#[cfg(feature = "serde")]
+fn serialize_cpu_mode<S: serde::Serializer>(mode: &CpuMode, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(match mode {
+        CpuMode::Real => "real",
+        CpuMode::Protected => "protected",
+        CpuMode::Long => "long",
+        CpuMode::Vm86 => "vm86",
+    })
+}
+
+#[cfg(feature = "serde")]
+fn serialize_assist_reason<S: serde::Serializer>(
+    reason: &Option<AssistReason>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match reason {
+        Some(reason) => s.serialize_some(match reason {
+            AssistReason::Io => "io",
+            AssistReason::Privileged => "privileged",
+            AssistReason::Interrupt => "interrupt",
+            AssistReason::Cpuid => "cpuid",
+            AssistReason::Msr => "msr",
+            AssistReason::Unsupported => "unsupported",
+        }),
+        None => s.serialize_none(),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serialize_exception<S: serde::Serializer>(
+    exception: &Exception,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_str(exception)
+}
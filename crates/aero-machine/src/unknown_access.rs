@@ -0,0 +1,227 @@
+//! Policy for guest accesses that no device decodes (see
+//! [`crate::Machine::set_unknown_access_config`]).
+//!
+//! Unclaimed ports and unmapped physical addresses keep their open-bus behaviour (reads return all
+//! ones, writes are dropped) under every policy. `Log` additionally records each access in a
+//! bounded, count-deduplicated ring; `Trap` records it and ends the current `run_slice` with
+//! [`crate::RunExit::UnknownPortIo`] / [`crate::RunExit::UnknownMmio`] once the executing batch
+//! finishes.
+//!
+//! Only accesses made by a vCPU while it executes guest code are considered. Firmware HLE, host
+//! debug accesses and device DMA outside of a CPU batch are ignored.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use crate::machine_events::{MachineEventCategory, MachineEventKind, MachineEventLog};
+
+/// Maximum number of distinct accesses retained in the unknown-access ring.
+pub const UNKNOWN_ACCESS_LOG_CAP: usize = 256;
+
+/// Ports ignored by default: the POST diagnostic port and the COM4 range that guests probe for a
+/// UART the machine does not model by default.
+pub const DEFAULT_PORT_IO_IGNORE: [RangeInclusive<u16>; 2] = [0x80..=0x80, 0x2E8..=0x2EF];
+
+/// What to do when the guest touches a port or physical address nothing decodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownAccessPolicy {
+    /// Open-bus behaviour only.
+    #[default]
+    Silent,
+    /// Record the access in the unknown-access log.
+    Log,
+    /// Record the access and stop the current `run_slice`.
+    Trap,
+}
+
+/// Host configuration for unknown-access handling. Not snapshotted; survives
+/// [`crate::Machine::reset`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAccessConfig {
+    pub port_io: UnknownAccessPolicy,
+    pub mmio: UnknownAccessPolicy,
+    /// Ports that never trigger the policy.
+    pub port_io_ignore: Vec<RangeInclusive<u16>>,
+    /// Physical addresses that never trigger the policy (matched on the first byte of the
+    /// access).
+    pub mmio_ignore: Vec<RangeInclusive<u64>>,
+}
+
+impl Default for UnknownAccessConfig {
+    fn default() -> Self {
+        Self {
+            port_io: UnknownAccessPolicy::Silent,
+            mmio: UnknownAccessPolicy::Silent,
+            port_io_ignore: DEFAULT_PORT_IO_IGNORE.to_vec(),
+            mmio_ignore: Vec::new(),
+        }
+    }
+}
+
+impl UnknownAccessConfig {
+    pub(crate) fn is_silent(&self) -> bool {
+        self.port_io == UnknownAccessPolicy::Silent && self.mmio == UnknownAccessPolicy::Silent
+    }
+}
+
+/// Address space of an [`UnknownAccess`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownAccessSpace {
+    PortIo,
+    Mmio,
+}
+
+/// One deduplicated unknown access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownAccess {
+    pub space: UnknownAccessSpace,
+    /// Port number or guest physical address.
+    pub addr: u64,
+    /// Access size in bytes.
+    pub size: u32,
+    pub write: bool,
+    /// RIP of the most recent occurrence.
+    pub rip: u64,
+    pub count: u64,
+}
+
+/// Contents of the unknown-access ring (see [`crate::Machine::take_unknown_access_log`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownAccessLog {
+    /// Least recently seen first.
+    pub entries: Vec<UnknownAccess>,
+    /// Distinct accesses evicted because the ring held [`UNKNOWN_ACCESS_LOG_CAP`] entries.
+    pub dropped: u64,
+}
+
+/// Shared between the machine and the bus hooks it installs.
+#[derive(Debug, Default)]
+pub(crate) struct UnknownAccessTracker {
+    config: RefCell<UnknownAccessConfig>,
+    // RIP of the vCPU currently executing a batch; `None` outside of CPU execution.
+    cpu_rip: Cell<Option<u64>>,
+    ring: RefCell<VecDeque<UnknownAccess>>,
+    dropped: Cell<u64>,
+    trap: Cell<Option<UnknownAccess>>,
+    // Receives every non-ignored access as a `MachineEventKind::UnknownIo`, regardless of policy.
+    events: Rc<MachineEventLog>,
+}
+
+impl UnknownAccessTracker {
+    pub(crate) fn with_events(events: Rc<MachineEventLog>) -> Self {
+        Self {
+            events,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn config(&self) -> UnknownAccessConfig {
+        self.config.borrow().clone()
+    }
+
+    pub(crate) fn set_config(&self, config: UnknownAccessConfig) {
+        *self.config.borrow_mut() = config;
+        self.trap.set(None);
+    }
+
+    pub(crate) fn is_silent(&self) -> bool {
+        self.config.borrow().is_silent()
+    }
+
+    /// Whether accesses need to be reported to the tracker at all (a non-silent policy, or the
+    /// event stream recording [`MachineEventCategory::UnknownIo`]).
+    pub(crate) fn is_active(&self) -> bool {
+        !self.is_silent() || self.events.wants(MachineEventCategory::UnknownIo)
+    }
+
+    /// Called at each instruction boundary of a CPU batch.
+    pub(crate) fn enter_cpu(&self, rip: u64) {
+        self.cpu_rip.set(Some(rip));
+    }
+
+    /// Called after a CPU batch.
+    pub(crate) fn leave_cpu(&self) {
+        self.cpu_rip.set(None);
+    }
+
+    /// The first access trapped since the previous call, if any.
+    pub(crate) fn take_trap(&self) -> Option<UnknownAccess> {
+        self.trap.take()
+    }
+
+    pub(crate) fn take_log(&self) -> UnknownAccessLog {
+        UnknownAccessLog {
+            entries: self.ring.borrow_mut().drain(..).collect(),
+            dropped: self.dropped.replace(0),
+        }
+    }
+
+    pub(crate) fn record(&self, space: UnknownAccessSpace, addr: u64, size: u32, write: bool) {
+        let Some(rip) = self.cpu_rip.get() else {
+            return;
+        };
+        let policy = {
+            let config = self.config.borrow();
+            let (policy, ignored) = match space {
+                UnknownAccessSpace::PortIo => (
+                    config.port_io,
+                    config
+                        .port_io_ignore
+                        .iter()
+                        .any(|range| range.contains(&(addr as u16))),
+                ),
+                UnknownAccessSpace::Mmio => (
+                    config.mmio,
+                    config.mmio_ignore.iter().any(|range| range.contains(&addr)),
+                ),
+            };
+            if ignored {
+                return;
+            }
+            policy
+        };
+        self.events.record(MachineEventKind::UnknownIo {
+            space,
+            addr,
+            size,
+            write,
+        });
+        if policy == UnknownAccessPolicy::Silent {
+            return;
+        }
+
+        let mut ring = self.ring.borrow_mut();
+        let existing = ring.iter().position(|entry| {
+            entry.space == space && entry.addr == addr && entry.size == size && entry.write == write
+        });
+        let entry = match existing.and_then(|idx| ring.remove(idx)) {
+            Some(entry) => UnknownAccess {
+                rip,
+                count: entry.count.saturating_add(1),
+                ..entry
+            },
+            None => {
+                if ring.len() == UNKNOWN_ACCESS_LOG_CAP {
+                    ring.pop_front();
+                    self.dropped.set(self.dropped.get().saturating_add(1));
+                }
+                UnknownAccess {
+                    space,
+                    addr,
+                    size,
+                    write,
+                    rip,
+                    count: 1,
+                }
+            }
+        };
+        ring.push_back(entry);
+
+        if policy == UnknownAccessPolicy::Trap {
+            let first = self.trap.take().unwrap_or(entry);
+            self.trap.set(Some(first));
+        }
+    }
+}
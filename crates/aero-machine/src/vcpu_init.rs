@@ -1,16 +1,27 @@
 //! Helpers for bringing vCPUs into architecturally plausible x86 reset / SIPI states.
 //!
 //! These helpers are used by SMP bring-up code (INIT + SIPI) to reset an AP back into a clean
-//! real-mode baseline and then enter at a SIPI vector address.
+//! real-mode baseline and then enter at a SIPI vector address, and by direct (firmware-less) boot
+//! to start the BSP in real, protected or long mode.
 
 #![allow(dead_code)]
 
-use aero_cpu_core::state::{CpuMode, CpuState, Segment, RFLAGS_RESERVED1};
+use aero_cpu_core::state::{
+    CpuMode, CpuState, Segment, CR0_PE, CR0_PG, CR4_PAE, EFER_LME, RFLAGS_RESERVED1, SEG_ACCESS_DB,
+    SEG_ACCESS_L,
+};
 use aero_cpu_core::CpuCore;
 
+use crate::direct_boot::{
+    DirectBootMode, DIRECT_BOOT_CODE32_SELECTOR, DIRECT_BOOT_CODE64_SELECTOR,
+    DIRECT_BOOT_DATA_SELECTOR, DIRECT_BOOT_GDT,
+};
+
 // VMX-style "segment access rights" encoding used throughout the emulator.
 const REAL_MODE_CODE_ACCESS: u32 = 0x9B; // present, DPL0, code, readable, accessed
 const REAL_MODE_DATA_ACCESS: u32 = 0x93; // present, DPL0, data, writable, accessed
+const SEG_ACCESS_G: u32 = 1 << 11; // 4KiB limit granularity
+const CR4_PSE: u64 = 1 << 4; // page size extensions (large pages)
 
 fn set_real_mode_segment(seg: &mut Segment, selector: u16, access: u32) {
     seg.selector = selector;
@@ -19,6 +30,13 @@ fn set_real_mode_segment(seg: &mut Segment, selector: u16, access: u32) {
     seg.access = access;
 }
 
+fn set_flat_segment(seg: &mut Segment, selector: u16, access: u32) {
+    seg.selector = selector;
+    seg.base = 0;
+    seg.limit = 0xFFFF_FFFF;
+    seg.access = access | SEG_ACCESS_G;
+}
+
 fn init_real_mode_segment_registers(state: &mut CpuState) {
     set_real_mode_segment(&mut state.segments.cs, 0, REAL_MODE_CODE_ACCESS);
     set_real_mode_segment(&mut state.segments.ds, 0, REAL_MODE_DATA_ACCESS);
@@ -88,6 +106,99 @@ pub(crate) fn init_ap_vcpu_from_sipi(cpu: &mut CpuCore, vector: u8) {
     cpu.state.clear_pending_bios_int();
 }
 
+/// Paging state for [`init_bsp_vcpu_for_direct_boot`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DirectBootPagingState {
+    pub(crate) cr3: u64,
+    /// Use PAE tables (always implied in long mode).
+    pub(crate) pae: bool,
+}
+
+/// Initialise the BSP to begin executing a direct boot payload at `entry`.
+///
+/// Protected and long mode use flat segments from the GDT written at `gdt_base` (see
+/// [`DIRECT_BOOT_GDT`]); the caller is responsible for writing the GDT and any page tables to
+/// guest memory. Like [`reset_ap_vcpu_to_init_state`], the APIC base MSR, TSC/TSC_AUX and
+/// [`CpuCore::time`] are preserved.
+pub(crate) fn init_bsp_vcpu_for_direct_boot(
+    cpu: &mut CpuCore,
+    mode: DirectBootMode,
+    entry: u64,
+    gpr: [u64; aero_cpu_core::state::GPR_COUNT],
+    rflags: u64,
+    gdt_base: u64,
+    paging: Option<DirectBootPagingState>,
+) {
+    let mut state = CpuState::new(CpuMode::Real);
+    state.msr.tsc = cpu.state.msr.tsc;
+    state.msr.tsc_aux = cpu.state.msr.tsc_aux;
+    state.msr.apic_base = cpu.state.msr.apic_base;
+    // There is no firmware to open the A20 gate, so direct boot always starts with it enabled.
+    state.a20_enabled = true;
+    state.gpr = gpr;
+
+    match mode {
+        DirectBootMode::Real => {
+            init_real_mode_segment_registers(&mut state);
+            set_real_mode_segment(
+                &mut state.segments.cs,
+                (entry >> 4) as u16,
+                REAL_MODE_CODE_ACCESS,
+            );
+            state.update_mode();
+            state.set_ip(entry & 0xF);
+        }
+        DirectBootMode::Protected32 | DirectBootMode::Long64 => {
+            state.tables.gdtr.base = gdt_base;
+            state.tables.gdtr.limit = (DIRECT_BOOT_GDT.len() * 8 - 1) as u16;
+
+            let data_access = REAL_MODE_DATA_ACCESS | SEG_ACCESS_DB;
+            for seg in [
+                &mut state.segments.ds,
+                &mut state.segments.es,
+                &mut state.segments.fs,
+                &mut state.segments.gs,
+                &mut state.segments.ss,
+            ] {
+                set_flat_segment(seg, DIRECT_BOOT_DATA_SELECTOR, data_access);
+            }
+            if mode == DirectBootMode::Long64 {
+                set_flat_segment(
+                    &mut state.segments.cs,
+                    DIRECT_BOOT_CODE64_SELECTOR,
+                    REAL_MODE_CODE_ACCESS | SEG_ACCESS_L,
+                );
+                state.msr.efer = EFER_LME;
+            } else {
+                set_flat_segment(
+                    &mut state.segments.cs,
+                    DIRECT_BOOT_CODE32_SELECTOR,
+                    REAL_MODE_CODE_ACCESS | SEG_ACCESS_DB,
+                );
+            }
+
+            state.control.cr0 = CR0_PE;
+            if let Some(paging) = paging {
+                state.control.cr3 = paging.cr3;
+                // Large pages (including the generated 2MiB identity map) require CR4.PSE.
+                state.control.cr4 |= CR4_PSE;
+                if paging.pae || mode == DirectBootMode::Long64 {
+                    state.control.cr4 |= CR4_PAE;
+                }
+                state.control.cr0 |= CR0_PG;
+            }
+            // Derives the final mode (and EFER.LMA in long mode) from CR0/CR4/EFER and CS.
+            state.update_mode();
+            state.set_ip(entry);
+        }
+    }
+    state.set_rflags(rflags | RFLAGS_RESERVED1);
+
+    cpu.state = state;
+    cpu.pending = Default::default();
+    cpu.time.set_tsc(cpu.state.msr.tsc);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,4 +280,37 @@ mod tests {
         assert_eq!(cpu.state.msr.tsc, 0x1122_3344_5566_7788);
         assert_eq!(cpu.time.read_tsc(), cpu.state.msr.tsc);
     }
+
+    #[test]
+    fn direct_boot_enters_long_mode_with_flat_segments() {
+        let mut cpu = CpuCore::new(CpuMode::Real);
+        cpu.state.msr.tsc = 0x1234;
+
+        let mut gpr = [0u64; aero_cpu_core::state::GPR_COUNT];
+        gpr[aero_cpu_core::state::gpr::RSP] = 0x8000;
+        init_bsp_vcpu_for_direct_boot(
+            &mut cpu,
+            DirectBootMode::Long64,
+            0x20_0000,
+            gpr,
+            0,
+            0x800,
+            Some(DirectBootPagingState {
+                cr3: 0x1_0000,
+                pae: true,
+            }),
+        );
+
+        assert_eq!(cpu.state.mode, CpuMode::Long);
+        assert_ne!(cpu.state.msr.efer & aero_cpu_core::state::EFER_LMA, 0);
+        assert_eq!(cpu.state.segments.cs.selector, DIRECT_BOOT_CODE64_SELECTOR);
+        assert_eq!(cpu.state.segments.ss.selector, DIRECT_BOOT_DATA_SELECTOR);
+        assert_eq!(cpu.state.tables.gdtr.base, 0x800);
+        assert_eq!(cpu.state.control.cr3, 0x1_0000);
+        assert_eq!(cpu.state.rip(), 0x20_0000);
+        assert_eq!(cpu.state.gpr[aero_cpu_core::state::gpr::RSP], 0x8000);
+        assert_ne!(cpu.state.rflags() & RFLAGS_RESERVED1, 0);
+        assert!(cpu.state.a20_enabled);
+        assert_eq!(cpu.state.msr.tsc, 0x1234);
+    }
 }
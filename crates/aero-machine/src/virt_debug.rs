@@ -0,0 +1,104 @@
+//! Virtual-address debug access (see [`crate::Machine::translate_virtual`]).
+//!
+//! Translations walk the selected vCPU's current page tables with a throwaway [`aero_mmu::Mmu`]
+//! synced from its CR0/CR3/CR4/EFER, using the MMU's side-effect-free probe walk: guest
+//! accessed/dirty bits, CR2 and the TLB used for execution are left untouched.
+
+use core::fmt;
+
+use aero_cpu_core::state::CpuState;
+use aero_mmu::{AccessType, MemoryBus, Mmu, WalkFault};
+
+pub use aero_mmu::PageTableLevel;
+
+// Page-fault error code bits (Intel SDM Vol. 3, 4.7).
+const PF_PRESENT: u32 = 1 << 0;
+const PF_RSVD: u32 = 1 << 3;
+
+/// Errors returned by [`crate::Machine::translate_virtual`],
+/// [`crate::Machine::read_virtual_bytes`] and [`crate::Machine::write_virtual_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtTranslateError {
+    /// The vCPU index is not below [`crate::Machine::cpu_count`].
+    InvalidCpuIndex(usize),
+    /// Non-canonical address in long mode.
+    NonCanonical(u64),
+    /// The paging-structure entry at `level` mapping `vaddr` is not present.
+    NotPresent { vaddr: u64, level: PageTableLevel },
+    /// The paging-structure entry at `level` mapping `vaddr` has reserved bits set.
+    ReservedBits { vaddr: u64, level: PageTableLevel },
+    /// A permission-respecting write hit a page that a supervisor write may not modify (read-only
+    /// with `CR0.WP` set).
+    WriteProtected { vaddr: u64, level: PageTableLevel },
+}
+
+impl fmt::Display for VirtTranslateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VirtTranslateError::InvalidCpuIndex(index) => {
+                write!(f, "cannot translate address: vCPU {index} does not exist")
+            }
+            VirtTranslateError::NonCanonical(vaddr) => {
+                write!(f, "virtual address {vaddr:#x} is not canonical")
+            }
+            VirtTranslateError::NotPresent { vaddr, level } => {
+                write!(
+                    f,
+                    "virtual address {vaddr:#x} is not mapped ({level:?} entry not present)"
+                )
+            }
+            VirtTranslateError::ReservedBits { vaddr, level } => write!(
+                f,
+                "virtual address {vaddr:#x} is mapped by a {level:?} entry with reserved bits set"
+            ),
+            VirtTranslateError::WriteProtected { vaddr, level } => write!(
+                f,
+                "virtual address {vaddr:#x} is write-protected ({level:?} mapping is read-only)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VirtTranslateError {}
+
+/// Translate `vaddr` through `state`'s page tables as a supervisor access.
+pub(crate) fn translate(
+    state: &CpuState,
+    bus: &mut impl MemoryBus,
+    vaddr: u64,
+    access: AccessType,
+) -> Result<u64, VirtTranslateError> {
+    let mut mmu = Mmu::new();
+    state.sync_mmu(&mut mmu);
+    mmu.walk_probe(bus, vaddr, access, 0)
+        .map_err(|fault| match fault {
+            WalkFault::NonCanonical(vaddr) => VirtTranslateError::NonCanonical(vaddr),
+            WalkFault::PageFault { fault, level } => {
+                let vaddr = fault.addr;
+                if fault.error_code & PF_PRESENT == 0 {
+                    VirtTranslateError::NotPresent { vaddr, level }
+                } else if fault.error_code & PF_RSVD != 0 {
+                    VirtTranslateError::ReservedBits { vaddr, level }
+                } else {
+                    VirtTranslateError::WriteProtected { vaddr, level }
+                }
+            }
+        })
+}
+
+/// Split `[vaddr, vaddr + len)` at 4KB page boundaries into `(vaddr, len)` chunks.
+pub(crate) fn page_chunks(vaddr: u64, len: usize) -> impl Iterator<Item = (u64, usize)> {
+    let mut vaddr = vaddr;
+    let mut remaining = len;
+    std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let to_boundary = 0x1000 - (vaddr & 0xfff) as usize;
+        let n = remaining.min(to_boundary);
+        let chunk = (vaddr, n);
+        vaddr = vaddr.wrapping_add(n as u64);
+        remaining -= n;
+        Some(chunk)
+    })
+}
@@ -0,0 +1,1003 @@
+//! Minimal virtio-9p (9P2000.L) file server exposing a host folder to the guest (see
+//! [`crate::MachineConfig::enable_virtio_9p`]).
+//!
+//! The device owns a single request queue. Every descriptor chain carries one T-message in its
+//! device-readable descriptors and receives the matching R-message in its device-writable
+//! descriptors; requests are served synchronously against a host [`SharedFolderBackend`], so no
+//! chain is ever held across `run_slice` calls.
+//!
+//! Only the subset of 9P2000.L needed for Linux `mount -t 9p -o trans=virtio,version=9p2000.L`
+//! to list, read, write, create and delete files is implemented. Everything else (links, renames,
+//! xattrs, locks) is answered with `Rlerror(EOPNOTSUPP)`.
+//!
+//! Backend paths are `/`-separated and relative to the shared folder root, which is the empty
+//! string. The server validates every component it receives from the guest, so backends never see
+//! empty, `.`, or `..` components.
+
+use std::collections::BTreeMap;
+
+use aero_virtio::devices::{VirtioDevice, VirtioDeviceError};
+use aero_virtio::memory::GuestMemory;
+use aero_virtio::pci::{VIRTIO_F_RING_INDIRECT_DESC, VIRTIO_F_VERSION_1};
+use aero_virtio::queue::{DescriptorChain, VirtQueue};
+
+pub const VIRTIO_DEVICE_TYPE_9P: u16 = 9;
+
+/// The device config space carries a mount tag.
+pub const VIRTIO_9P_F_MOUNT_TAG: u64 = 1 << 0;
+
+/// Default mount tag (`mount -t 9p aero /mnt -o trans=virtio`).
+pub const DEFAULT_VIRTIO_9P_MOUNT_TAG: &str = "aero";
+
+// Longer mount tags are truncated.
+const MAX_MOUNT_TAG_LEN: usize = 32;
+
+// Host-side safety: cap the negotiated message size (and therefore every per-request buffer).
+const MAX_MSIZE: u32 = 128 * 1024;
+
+// Host-side safety: cap the fid table and the length of any path the guest can build.
+const MAX_FIDS: usize = 4096;
+const MAX_PATH_LEN: usize = 4096;
+
+// `Twalk` may carry at most this many names (`P9_MAXWELEM`).
+const MAX_WALK_NAMES: usize = 16;
+
+// Message header: size[4] type[1] tag[2].
+const HEADER_LEN: usize = 7;
+
+const P9_RLERROR: u8 = 7;
+const P9_TSTATFS: u8 = 8;
+const P9_TLOPEN: u8 = 12;
+const P9_TLCREATE: u8 = 14;
+const P9_TGETATTR: u8 = 24;
+const P9_TSETATTR: u8 = 26;
+const P9_TREADDIR: u8 = 40;
+const P9_TFSYNC: u8 = 50;
+const P9_TMKDIR: u8 = 72;
+const P9_TUNLINKAT: u8 = 76;
+const P9_TVERSION: u8 = 100;
+const P9_TATTACH: u8 = 104;
+const P9_TFLUSH: u8 = 108;
+const P9_TWALK: u8 = 110;
+const P9_TREAD: u8 = 116;
+const P9_TWRITE: u8 = 118;
+const P9_TCLUNK: u8 = 120;
+const P9_TREMOVE: u8 = 122;
+
+const P9_QTDIR: u8 = 0x80;
+const P9_QTFILE: u8 = 0x00;
+
+const P9_GETATTR_BASIC: u64 = 0x0000_07ff;
+const P9_ATTR_SIZE: u32 = 1 << 3;
+
+// Linux open(2) flags as carried by `Tlopen`/`Tlcreate`.
+const P9_DOTL_ACCMODE: u32 = 0o3;
+const P9_DOTL_TRUNC: u32 = 0o1000;
+
+const AT_REMOVEDIR: u32 = 0x200;
+
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+
+const V9FS_MAGIC: u32 = 0x0102_1997;
+
+// Linux errno values reported through `Rlerror`.
+const ENOENT: u32 = 2;
+const EIO: u32 = 5;
+const ENXIO: u32 = 6;
+const EBADF: u32 = 9;
+const EACCES: u32 = 13;
+const EBUSY: u32 = 16;
+const EEXIST: u32 = 17;
+const ENOTDIR: u32 = 20;
+const EISDIR: u32 = 21;
+const EINVAL: u32 = 22;
+const EMFILE: u32 = 24;
+const EROFS: u32 = 30;
+const ENAMETOOLONG: u32 = 36;
+const ENOTEMPTY: u32 = 39;
+const EOPNOTSUPP: u32 = 95;
+
+/// Errors reported by a [`SharedFolderBackend`]; each maps onto the Linux errno the guest sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedFolderError {
+    NotFound,
+    AlreadyExists,
+    NotADirectory,
+    IsADirectory,
+    DirectoryNotEmpty,
+    PermissionDenied,
+    Unsupported,
+    Io,
+}
+
+impl SharedFolderError {
+    fn errno(self) -> u32 {
+        match self {
+            SharedFolderError::NotFound => ENOENT,
+            SharedFolderError::AlreadyExists => EEXIST,
+            SharedFolderError::NotADirectory => ENOTDIR,
+            SharedFolderError::IsADirectory => EISDIR,
+            SharedFolderError::DirectoryNotEmpty => ENOTEMPTY,
+            SharedFolderError::PermissionDenied => EACCES,
+            SharedFolderError::Unsupported => EOPNOTSUPP,
+            SharedFolderError::Io => EIO,
+        }
+    }
+}
+
+impl From<SharedFolderError> for u32 {
+    fn from(err: SharedFolderError) -> Self {
+        err.errno()
+    }
+}
+
+/// Metadata returned by [`SharedFolderBackend::stat`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharedFileStat {
+    pub is_dir: bool,
+    /// File size in bytes (ignored for directories).
+    pub size: u64,
+    /// Last modification time in nanoseconds since the Unix epoch, or 0 if unknown.
+    pub mtime_ns: u64,
+}
+
+/// One entry returned by [`SharedFolderBackend::readdir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedDirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// How the guest opens a file through [`SharedFolderBackend::open`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharedOpenMode {
+    /// The guest intends to write through this open file.
+    pub write: bool,
+    /// Discard the current contents of the file.
+    pub truncate: bool,
+}
+
+/// Host storage behind the virtio-9p device.
+///
+/// Paths are `/`-separated, relative to the shared folder root (the root itself is `""`), and
+/// never contain empty, `.` or `..` components. Backends are stateless with respect to open files:
+/// every read and write names its path, so the host (e.g. OPFS in the browser) may keep or drop
+/// its own handles as it sees fit.
+pub trait SharedFolderBackend {
+    /// Check that the file at `path` can be opened with `mode`, truncating it if requested.
+    fn open(&mut self, path: &str, mode: SharedOpenMode) -> Result<(), SharedFolderError>;
+
+    /// Read up to `buf.len()` bytes at `offset`. Returns the number of bytes read (0 at EOF).
+    fn read(&mut self, path: &str, offset: u64, buf: &mut [u8])
+        -> Result<usize, SharedFolderError>;
+
+    /// Write `data` at `offset`, extending the file if needed. Returns the number of bytes written.
+    fn write(&mut self, path: &str, offset: u64, data: &[u8]) -> Result<usize, SharedFolderError>;
+
+    /// List the entries of the directory at `path`, excluding `.` and `..`.
+    fn readdir(&mut self, path: &str) -> Result<Vec<SharedDirEntry>, SharedFolderError>;
+
+    fn stat(&mut self, path: &str) -> Result<SharedFileStat, SharedFolderError>;
+
+    /// Create an empty file or directory at `path`; fails if it already exists.
+    fn create(&mut self, path: &str, is_dir: bool) -> Result<(), SharedFolderError>;
+
+    /// Remove the file or empty directory at `path`.
+    fn remove(&mut self, path: &str) -> Result<(), SharedFolderError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fid {
+    path: String,
+    is_dir: bool,
+    opened: bool,
+    writable: bool,
+}
+
+type P9Result = Result<Vec<u8>, u32>;
+
+/// virtio-9p device model serving one [`SharedFolderBackend`].
+pub struct Virtio9p {
+    features: u64,
+    mount_tag: Vec<u8>,
+    read_only: bool,
+    backend: Option<Box<dyn SharedFolderBackend>>,
+    msize: u32,
+    fids: BTreeMap<u32, Fid>,
+}
+
+impl Virtio9p {
+    pub fn new(mount_tag: &str, read_only: bool) -> Self {
+        let mut mount_tag = mount_tag.as_bytes().to_vec();
+        mount_tag.truncate(MAX_MOUNT_TAG_LEN);
+        Self {
+            features: 0,
+            mount_tag,
+            read_only,
+            backend: None,
+            msize: MAX_MSIZE,
+            fids: BTreeMap::new(),
+        }
+    }
+
+    /// Attach the host folder. Fids the guest holds from a previous backend become invalid.
+    pub fn set_backend(&mut self, backend: Box<dyn SharedFolderBackend>) {
+        self.backend = Some(backend);
+        self.fids.clear();
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn backend(&mut self) -> Result<&mut dyn SharedFolderBackend, u32> {
+        match self.backend.as_deref_mut() {
+            Some(backend) => Ok(backend),
+            None => Err(ENXIO),
+        }
+    }
+
+    fn fid(&self, fid: u32) -> Result<&Fid, u32> {
+        self.fids.get(&fid).ok_or(EBADF)
+    }
+
+    fn insert_fid(&mut self, fid: u32, state: Fid) -> Result<(), u32> {
+        if !self.fids.contains_key(&fid) && self.fids.len() >= MAX_FIDS {
+            return Err(EMFILE);
+        }
+        self.fids.insert(fid, state);
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), u32> {
+        if self.read_only {
+            Err(EROFS)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_request(
+        chain: &DescriptorChain,
+        mem: &dyn GuestMemory,
+    ) -> Result<Vec<u8>, VirtioDeviceError> {
+        let mut bytes = Vec::new();
+        for d in chain.descriptors() {
+            if d.is_write_only() {
+                break;
+            }
+            let remaining = MAX_MSIZE as usize - bytes.len();
+            let len = (d.len as usize).min(remaining);
+            let start = bytes.len();
+            bytes.resize(start + len, 0);
+            mem.read(d.addr, &mut bytes[start..])
+                .map_err(|_| VirtioDeviceError::IoError)?;
+        }
+        Ok(bytes)
+    }
+
+    fn write_reply(
+        chain: &DescriptorChain,
+        mem: &mut dyn GuestMemory,
+        reply: &[u8],
+    ) -> Result<u32, VirtioDeviceError> {
+        let mut written = 0usize;
+        for d in chain.descriptors().iter().filter(|d| d.is_write_only()) {
+            if written == reply.len() {
+                break;
+            }
+            let len = (d.len as usize).min(reply.len() - written);
+            mem.write(d.addr, &reply[written..written + len])
+                .map_err(|_| VirtioDeviceError::IoError)?;
+            written += len;
+        }
+        Ok(written as u32)
+    }
+
+    /// Serve one T-message. `reply_cap` bounds the size of the R-message.
+    fn handle_message(&mut self, msg: &[u8], reply_cap: usize) -> Vec<u8> {
+        if msg.len() < HEADER_LEN {
+            // Without a tag there is nothing meaningful to reply to.
+            return Vec::new();
+        }
+        let size = u32::from_le_bytes(msg[0..4].try_into().unwrap()) as usize;
+        let ty = msg[4];
+        let tag = u16::from_le_bytes([msg[5], msg[6]]);
+
+        let result = if size < HEADER_LEN || size > msg.len() {
+            Err(EINVAL)
+        } else {
+            let mut r = Reader::new(&msg[HEADER_LEN..size]);
+            // Rread/Rreaddir: header + count[4].
+            let data_cap = reply_cap
+                .min(self.msize as usize)
+                .saturating_sub(HEADER_LEN + 4);
+            self.dispatch(ty, &mut r, data_cap)
+        };
+
+        let (reply_ty, body) = match result {
+            Ok(body) => (ty.wrapping_add(1), body),
+            Err(errno) => (P9_RLERROR, errno.to_le_bytes().to_vec()),
+        };
+        let mut reply = Vec::with_capacity(HEADER_LEN + body.len());
+        reply.extend_from_slice(&((HEADER_LEN + body.len()) as u32).to_le_bytes());
+        reply.push(reply_ty);
+        reply.extend_from_slice(&tag.to_le_bytes());
+        reply.extend_from_slice(&body);
+        if reply.len() > reply_cap {
+            // The guest did not post enough writable space for the reply.
+            reply.truncate(HEADER_LEN);
+            reply[0..4].copy_from_slice(&((HEADER_LEN + 4) as u32).to_le_bytes());
+            reply[4] = P9_RLERROR;
+            reply.extend_from_slice(&EIO.to_le_bytes());
+        }
+        reply
+    }
+
+    fn dispatch(&mut self, ty: u8, r: &mut Reader<'_>, data_cap: usize) -> P9Result {
+        match ty {
+            P9_TVERSION => self.version(r),
+            P9_TATTACH => self.attach(r),
+            P9_TWALK => self.walk(r),
+            P9_TLOPEN => self.lopen(r),
+            P9_TLCREATE => self.lcreate(r),
+            P9_TMKDIR => self.mkdir(r),
+            P9_TREAD => self.read(r, data_cap),
+            P9_TWRITE => self.write(r),
+            P9_TREADDIR => self.readdir(r, data_cap),
+            P9_TGETATTR => self.getattr(r),
+            P9_TSETATTR => self.setattr(r),
+            P9_TSTATFS => self.statfs(r),
+            P9_TCLUNK => {
+                let fid = r.u32()?;
+                self.fids.remove(&fid).ok_or(EBADF)?;
+                Ok(Vec::new())
+            }
+            P9_TREMOVE => self.remove(r),
+            P9_TUNLINKAT => self.unlinkat(r),
+            P9_TFSYNC => {
+                self.fid(r.u32()?)?;
+                Ok(Vec::new())
+            }
+            P9_TFLUSH => {
+                // Requests complete synchronously, so there is never anything left to cancel.
+                r.u16()?;
+                Ok(Vec::new())
+            }
+            _ => Err(EOPNOTSUPP),
+        }
+    }
+
+    fn version(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let msize = r.u32()?;
+        let version = r.string()?;
+        // A version request aborts every outstanding fid.
+        self.fids.clear();
+        self.msize = msize.min(MAX_MSIZE);
+        let version = if version.starts_with("9P2000.L") {
+            "9P2000.L"
+        } else {
+            "unknown"
+        };
+        let mut w = Vec::new();
+        w.extend_from_slice(&self.msize.to_le_bytes());
+        put_string(&mut w, version);
+        Ok(w)
+    }
+
+    fn attach(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let _afid = r.u32()?;
+        let _uname = r.string()?;
+        let aname = r.string()?;
+        if self.fids.contains_key(&fid) {
+            return Err(EBADF);
+        }
+        // Only the folder root is exported.
+        if !aname.is_empty() && aname != "/" {
+            return Err(ENOENT);
+        }
+        let stat = self.backend()?.stat("")?;
+        if !stat.is_dir {
+            return Err(ENOTDIR);
+        }
+        self.insert_fid(
+            fid,
+            Fid {
+                path: String::new(),
+                is_dir: true,
+                opened: false,
+                writable: false,
+            },
+        )?;
+        let mut w = Vec::new();
+        put_qid(&mut w, "", &stat);
+        Ok(w)
+    }
+
+    fn walk(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let newfid = r.u32()?;
+        let nwname = usize::from(r.u16()?);
+        if nwname > MAX_WALK_NAMES {
+            return Err(EINVAL);
+        }
+        let names = (0..nwname)
+            .map(|_| r.string())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let start = self.fid(fid)?.clone();
+        if newfid != fid && self.fids.contains_key(&newfid) {
+            return Err(EBADF);
+        }
+
+        let mut path = start.path.clone();
+        let mut is_dir = start.is_dir;
+        let mut qids = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let step = if !is_dir {
+                Err(ENOTDIR)
+            } else {
+                walk_component(&path, name).and_then(|next| {
+                    let stat = self.backend()?.stat(&next)?;
+                    Ok((next, stat))
+                })
+            };
+            match step {
+                Ok((next, stat)) => {
+                    put_qid(&mut qids, &next, &stat);
+                    path = next;
+                    is_dir = stat.is_dir;
+                }
+                // Only a failure on the first name is an error; otherwise reply with the qids of
+                // the names that were walked and leave `newfid` unset.
+                Err(errno) if i == 0 => return Err(errno),
+                Err(_) => {
+                    let mut w = Vec::new();
+                    w.extend_from_slice(&(i as u16).to_le_bytes());
+                    w.extend_from_slice(&qids);
+                    return Ok(w);
+                }
+            }
+        }
+
+        self.insert_fid(
+            newfid,
+            Fid {
+                path,
+                is_dir,
+                opened: false,
+                writable: false,
+            },
+        )?;
+        let mut w = Vec::new();
+        w.extend_from_slice(&(names.len() as u16).to_le_bytes());
+        w.extend_from_slice(&qids);
+        Ok(w)
+    }
+
+    fn lopen(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let flags = r.u32()?;
+        let state = self.fid(fid)?.clone();
+        if state.opened {
+            return Err(EBADF);
+        }
+        let mode = SharedOpenMode {
+            write: flags & P9_DOTL_ACCMODE != 0,
+            truncate: flags & P9_DOTL_TRUNC != 0,
+        };
+        if mode.write || mode.truncate {
+            if state.is_dir {
+                return Err(EISDIR);
+            }
+            self.check_writable()?;
+        }
+        if !state.is_dir {
+            self.backend()?.open(&state.path, mode)?;
+        }
+        let stat = self.backend()?.stat(&state.path)?;
+        if let Some(state) = self.fids.get_mut(&fid) {
+            state.opened = true;
+            state.writable = mode.write;
+        }
+        let mut w = Vec::new();
+        put_qid(&mut w, &state.path, &stat);
+        // iounit 0: the client derives its I/O size from msize.
+        w.extend_from_slice(&0u32.to_le_bytes());
+        Ok(w)
+    }
+
+    fn lcreate(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let name = r.string()?;
+        let flags = r.u32()?;
+        let _mode = r.u32()?;
+        let _gid = r.u32()?;
+        let dir = self.fid(fid)?.clone();
+        if !dir.is_dir {
+            return Err(ENOTDIR);
+        }
+        if dir.opened {
+            return Err(EBADF);
+        }
+        self.check_writable()?;
+        let path = child_path(&dir.path, name)?;
+        let backend = self.backend()?;
+        backend.create(&path, false)?;
+        let mode = SharedOpenMode {
+            write: flags & P9_DOTL_ACCMODE != 0,
+            truncate: false,
+        };
+        backend.open(&path, mode)?;
+        let stat = backend.stat(&path)?;
+        // The fid now refers to the new, opened file.
+        self.fids.insert(
+            fid,
+            Fid {
+                path: path.clone(),
+                is_dir: false,
+                opened: true,
+                writable: mode.write,
+            },
+        );
+        let mut w = Vec::new();
+        put_qid(&mut w, &path, &stat);
+        w.extend_from_slice(&0u32.to_le_bytes());
+        Ok(w)
+    }
+
+    fn mkdir(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let dfid = r.u32()?;
+        let name = r.string()?;
+        let _mode = r.u32()?;
+        let _gid = r.u32()?;
+        let dir = self.fid(dfid)?.clone();
+        if !dir.is_dir {
+            return Err(ENOTDIR);
+        }
+        self.check_writable()?;
+        let path = child_path(&dir.path, name)?;
+        let backend = self.backend()?;
+        backend.create(&path, true)?;
+        let stat = backend.stat(&path)?;
+        let mut w = Vec::new();
+        put_qid(&mut w, &path, &stat);
+        Ok(w)
+    }
+
+    fn read(&mut self, r: &mut Reader<'_>, data_cap: usize) -> P9Result {
+        let fid = r.u32()?;
+        let offset = r.u64()?;
+        let count = r.u32()? as usize;
+        let state = self.fid(fid)?.clone();
+        if !state.opened {
+            return Err(EBADF);
+        }
+        if state.is_dir {
+            return Err(EISDIR);
+        }
+        let mut data = vec![0u8; count.min(data_cap)];
+        let n = self
+            .backend()?
+            .read(&state.path, offset, &mut data)?
+            .min(data.len());
+        let mut w = Vec::with_capacity(4 + n);
+        w.extend_from_slice(&(n as u32).to_le_bytes());
+        w.extend_from_slice(&data[..n]);
+        Ok(w)
+    }
+
+    fn write(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let offset = r.u64()?;
+        let count = r.u32()? as usize;
+        let data = r.bytes(count)?;
+        let state = self.fid(fid)?.clone();
+        if !state.opened || !state.writable {
+            return Err(EBADF);
+        }
+        self.check_writable()?;
+        let n = self.backend()?.write(&state.path, offset, data)?.min(count);
+        Ok((n as u32).to_le_bytes().to_vec())
+    }
+
+    fn readdir(&mut self, r: &mut Reader<'_>, data_cap: usize) -> P9Result {
+        let fid = r.u32()?;
+        let offset = r.u64()?;
+        let count = (r.u32()? as usize).min(data_cap);
+        let state = self.fid(fid)?.clone();
+        if !state.is_dir {
+            return Err(ENOTDIR);
+        }
+        if !state.opened {
+            return Err(EBADF);
+        }
+
+        let backend = self.backend()?;
+        let parent = parent_path(&state.path);
+        let mut entries = vec![
+            (".".to_string(), state.path.clone(), true),
+            ("..".to_string(), parent.to_string(), true),
+        ];
+        for entry in backend.readdir(&state.path)? {
+            let path = join_path(&state.path, &entry.name);
+            entries.push((entry.name, path, entry.is_dir));
+        }
+
+        // Entry offsets are indices into this listing; the client resumes from the last one it
+        // received.
+        let mut data = Vec::new();
+        let skip = usize::try_from(offset).unwrap_or(usize::MAX);
+        for (index, (name, path, is_dir)) in entries.iter().enumerate().skip(skip) {
+            let mut entry = Vec::new();
+            let stat = SharedFileStat {
+                is_dir: *is_dir,
+                ..Default::default()
+            };
+            put_qid(&mut entry, path, &stat);
+            entry.extend_from_slice(&(index as u64 + 1).to_le_bytes());
+            entry.push(if *is_dir { DT_DIR } else { DT_REG });
+            put_string(&mut entry, name);
+            if data.len() + entry.len() > count {
+                break;
+            }
+            data.extend_from_slice(&entry);
+        }
+        let mut w = Vec::with_capacity(4 + data.len());
+        w.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        w.extend_from_slice(&data);
+        Ok(w)
+    }
+
+    fn getattr(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let _request_mask = r.u64()?;
+        let path = self.fid(fid)?.path.clone();
+        let stat = self.backend()?.stat(&path)?;
+
+        let perm = match (stat.is_dir, self.read_only) {
+            (true, false) => 0o040_755u32,
+            (true, true) => 0o040_555,
+            (false, false) => 0o100_644,
+            (false, true) => 0o100_444,
+        };
+        let size = if stat.is_dir { 0 } else { stat.size };
+        let mtime_sec = stat.mtime_ns / 1_000_000_000;
+        let mtime_nsec = stat.mtime_ns % 1_000_000_000;
+
+        let mut w = Vec::new();
+        w.extend_from_slice(&P9_GETATTR_BASIC.to_le_bytes());
+        put_qid(&mut w, &path, &stat);
+        w.extend_from_slice(&perm.to_le_bytes());
+        w.extend_from_slice(&0u32.to_le_bytes()); // uid
+        w.extend_from_slice(&0u32.to_le_bytes()); // gid
+        w.extend_from_slice(&(if stat.is_dir { 2u64 } else { 1 }).to_le_bytes()); // nlink
+        w.extend_from_slice(&0u64.to_le_bytes()); // rdev
+        w.extend_from_slice(&size.to_le_bytes());
+        w.extend_from_slice(&4096u64.to_le_bytes()); // blksize
+        w.extend_from_slice(&size.div_ceil(512).to_le_bytes()); // blocks
+                                                                // atime, mtime, ctime all report the backend's modification time.
+        for _ in 0..3 {
+            w.extend_from_slice(&mtime_sec.to_le_bytes());
+            w.extend_from_slice(&mtime_nsec.to_le_bytes());
+        }
+        // btime, gen, data_version (not part of the basic set).
+        for _ in 0..4 {
+            w.extend_from_slice(&0u64.to_le_bytes());
+        }
+        Ok(w)
+    }
+
+    fn setattr(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        let valid = r.u32()?;
+        let _mode = r.u32()?;
+        let _uid = r.u32()?;
+        let _gid = r.u32()?;
+        let size = r.u64()?;
+        let state = self.fid(fid)?.clone();
+        // The backend has no ownership, permission or timestamp metadata, so only truncation is
+        // honoured; other attribute changes are accepted and ignored.
+        if valid & P9_ATTR_SIZE == 0 {
+            return Ok(Vec::new());
+        }
+        if state.is_dir {
+            return Err(EISDIR);
+        }
+        self.check_writable()?;
+        let backend = self.backend()?;
+        if size == 0 {
+            backend.open(
+                &state.path,
+                SharedOpenMode {
+                    write: true,
+                    truncate: true,
+                },
+            )?;
+        } else if backend.stat(&state.path)?.size != size {
+            return Err(EOPNOTSUPP);
+        }
+        Ok(Vec::new())
+    }
+
+    fn statfs(&mut self, r: &mut Reader<'_>) -> P9Result {
+        self.fid(r.u32()?)?;
+        let mut w = Vec::new();
+        w.extend_from_slice(&V9FS_MAGIC.to_le_bytes());
+        w.extend_from_slice(&4096u32.to_le_bytes()); // bsize
+                                                     // blocks, bfree, bavail, files, ffree, fsid: the backend exposes no capacity information.
+        for _ in 0..6 {
+            w.extend_from_slice(&0u64.to_le_bytes());
+        }
+        w.extend_from_slice(&255u32.to_le_bytes()); // namelen
+        Ok(w)
+    }
+
+    fn remove(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let fid = r.u32()?;
+        // `Tremove` clunks the fid even if the removal fails.
+        let state = self.fids.remove(&fid).ok_or(EBADF)?;
+        self.check_writable()?;
+        if state.path.is_empty() {
+            return Err(EBUSY);
+        }
+        self.backend()?.remove(&state.path)?;
+        Ok(Vec::new())
+    }
+
+    fn unlinkat(&mut self, r: &mut Reader<'_>) -> P9Result {
+        let dirfid = r.u32()?;
+        let name = r.string()?;
+        let flags = r.u32()?;
+        let dir = self.fid(dirfid)?.clone();
+        if !dir.is_dir {
+            return Err(ENOTDIR);
+        }
+        self.check_writable()?;
+        let path = child_path(&dir.path, name)?;
+        let backend = self.backend()?;
+        let stat = backend.stat(&path)?;
+        match (stat.is_dir, flags & AT_REMOVEDIR != 0) {
+            (true, false) => return Err(EISDIR),
+            (false, true) => return Err(ENOTDIR),
+            _ => {}
+        }
+        backend.remove(&path)?;
+        Ok(Vec::new())
+    }
+
+    fn config_bytes(&self) -> Vec<u8> {
+        // struct virtio_9p_config { le16 tag_len; u8 tag[tag_len]; }
+        let mut cfg = Vec::with_capacity(2 + self.mount_tag.len());
+        cfg.extend_from_slice(&(self.mount_tag.len() as u16).to_le_bytes());
+        cfg.extend_from_slice(&self.mount_tag);
+        cfg
+    }
+}
+
+impl VirtioDevice for Virtio9p {
+    fn device_type(&self) -> u16 {
+        VIRTIO_DEVICE_TYPE_9P
+    }
+
+    fn device_features(&self) -> u64 {
+        VIRTIO_F_VERSION_1 | VIRTIO_F_RING_INDIRECT_DESC | VIRTIO_9P_F_MOUNT_TAG
+    }
+
+    fn set_features(&mut self, features: u64) {
+        self.features = features;
+    }
+
+    fn num_queues(&self) -> u16 {
+        1
+    }
+
+    fn queue_max_size(&self, _queue: u16) -> u16 {
+        128
+    }
+
+    fn process_queue(
+        &mut self,
+        queue_index: u16,
+        chain: DescriptorChain,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        if queue_index != 0 {
+            return Err(VirtioDeviceError::Unsupported);
+        }
+        let request = Self::read_request(&chain, &*mem)?;
+        let reply_cap = chain
+            .descriptors()
+            .iter()
+            .filter(|d| d.is_write_only())
+            .fold(0usize, |acc, d| acc.saturating_add(d.len as usize));
+        let reply = self.handle_message(&request, reply_cap);
+        let written = Self::write_reply(&chain, mem, &reply)?;
+        queue
+            .add_used(mem, chain.head_index(), written)
+            .map_err(|_| VirtioDeviceError::IoError)
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        let cfg = self.config_bytes();
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = usize::try_from(offset)
+                .ok()
+                .and_then(|off| off.checked_add(i))
+                .and_then(|off| cfg.get(off))
+                .copied()
+                .unwrap_or(0);
+        }
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {
+        // The mount tag is read-only.
+    }
+
+    fn reset(&mut self) {
+        // The backend is host policy and survives device resets (e.g. guest reboots).
+        self.features = 0;
+        self.msize = MAX_MSIZE;
+        self.fids.clear();
+    }
+
+    fn snapshot_device_state(&self) -> Option<Vec<u8>> {
+        // - byte0: version
+        // - bytes1..5: msize
+        // - bytes5..9: fid count
+        // - per fid: fid[4] flags[1] path_len[4] path
+        //
+        // The backend itself is host state and is not part of the snapshot.
+        let mut out = vec![1];
+        out.extend_from_slice(&self.msize.to_le_bytes());
+        out.extend_from_slice(&(self.fids.len() as u32).to_le_bytes());
+        for (&fid, state) in &self.fids {
+            out.extend_from_slice(&fid.to_le_bytes());
+            out.push(
+                u8::from(state.is_dir)
+                    | (u8::from(state.opened) << 1)
+                    | (u8::from(state.writable) << 2),
+            );
+            out.extend_from_slice(&(state.path.len() as u32).to_le_bytes());
+            out.extend_from_slice(state.path.as_bytes());
+        }
+        Some(out)
+    }
+
+    fn restore_device_state(&mut self, bytes: &[u8]) {
+        let mut r = Reader::new(bytes);
+        let Ok(1) = r.u8() else {
+            return;
+        };
+        let (Ok(msize), Ok(count)) = (r.u32(), r.u32()) else {
+            return;
+        };
+        let mut fids = BTreeMap::new();
+        for _ in 0..(count as usize).min(MAX_FIDS) {
+            let (Ok(fid), Ok(flags), Ok(len)) = (r.u32(), r.u8(), r.u32()) else {
+                return;
+            };
+            if len as usize > MAX_PATH_LEN {
+                return;
+            }
+            let Some(path) = r
+                .bytes(len as usize)
+                .ok()
+                .and_then(|b| std::str::from_utf8(b).ok())
+            else {
+                return;
+            };
+            fids.insert(
+                fid,
+                Fid {
+                    path: path.to_string(),
+                    is_dir: flags & 1 != 0,
+                    opened: flags & 2 != 0,
+                    writable: flags & 4 != 0,
+                },
+            );
+        }
+        self.msize = msize.min(MAX_MSIZE);
+        self.fids = fids;
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
+
+/// Little-endian 9P field reader; running out of bytes is reported as `EINVAL`.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], u32> {
+        if len > self.buf.len() {
+            return Err(EINVAL);
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, u32> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, u32> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, u32> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<&'a str, u32> {
+        let len = usize::from(self.u16()?);
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| EINVAL)
+    }
+}
+
+fn put_string(w: &mut Vec<u8>, s: &str) {
+    w.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    w.extend_from_slice(s.as_bytes());
+}
+
+fn put_qid(w: &mut Vec<u8>, path: &str, stat: &SharedFileStat) {
+    // qid.path only needs to be a stable unique id per file; hash the backend path (FNV-1a).
+    let id = path.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    w.push(if stat.is_dir { P9_QTDIR } else { P9_QTFILE });
+    w.extend_from_slice(&0u32.to_le_bytes());
+    w.extend_from_slice(&id.to_le_bytes());
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+fn parent_path(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+/// Path of a new entry `name` inside `dir`.
+fn child_path(dir: &str, name: &str) -> Result<String, u32> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        return Err(EINVAL);
+    }
+    let path = join_path(dir, name);
+    if path.len() > MAX_PATH_LEN {
+        return Err(ENAMETOOLONG);
+    }
+    Ok(path)
+}
+
+/// Path reached by walking `name` from `dir`; `..` stops at the shared folder root.
+fn walk_component(dir: &str, name: &str) -> Result<String, u32> {
+    match name {
+        "." => Ok(dir.to_string()),
+        ".." => Ok(parent_path(dir).to_string()),
+        _ => child_path(dir, name),
+    }
+}
@@ -0,0 +1,114 @@
+//! Guest hang watchdog (see [`crate::Machine::set_hang_watchdog`]).
+//!
+//! The watchdog samples coarse progress indicators at `run_slice` boundaries only: the BSP's
+//! linear RIP, the number of injected external interrupts, and the number of guest port I/O
+//! accesses (see [`crate::MachinePerfCounters`]). If none of them change for the configured amount
+//! of guest time (measured on the BSP's deterministic TSC), `run_slice` reports
+//! [`crate::RunExit::HangSuspected`].
+//!
+//! A BSP halted with `IF=1` is waiting for an interrupt and always counts as progress, so HLT-based
+//! idle loops never trigger the watchdog.
+
+use std::collections::VecDeque;
+
+/// Number of recently injected interrupt vectors retained for [`HangDetails::recent_vectors`].
+pub const HANG_WATCHDOG_RECENT_VECTORS: usize = 16;
+
+/// RIP movement (in bytes of linear address) that still counts as "spinning in place".
+pub const HANG_WATCHDOG_RIP_WINDOW: u64 = 256;
+
+/// Architectural state of one vCPU when a hang was suspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HangVcpuState {
+    pub rip: u64,
+    /// Current privilege level (ring).
+    pub cpl: u8,
+    /// `RFLAGS.IF`.
+    pub interrupts_enabled: bool,
+    pub halted: bool,
+}
+
+/// Diagnostics captured when the hang watchdog fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HangDetails {
+    /// Guest time since the last observed progress, in nanoseconds.
+    pub stalled_ns: u64,
+    /// Per-vCPU state, indexed by vCPU (index 0 is the BSP).
+    pub vcpus: Vec<HangVcpuState>,
+    /// The most recently injected external interrupt vectors, oldest first (at most
+    /// [`HANG_WATCHDOG_RECENT_VECTORS`]).
+    pub recent_vectors: Vec<u8>,
+}
+
+/// Progress indicators sampled at a `run_slice` boundary.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProgressSample {
+    pub(crate) tsc: u64,
+    pub(crate) linear_rip: u64,
+    pub(crate) interrupt_injections: u64,
+    pub(crate) port_io: u64,
+    /// BSP is halted with interrupts enabled (idle, waiting for an interrupt).
+    pub(crate) idle: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HangWatchdog {
+    threshold_ns: u64,
+    // Last sample that showed progress; `None` takes a fresh baseline at the next boundary (after
+    // arming, reset or snapshot restore).
+    anchor: Option<ProgressSample>,
+    recent_vectors: VecDeque<u8>,
+}
+
+impl HangWatchdog {
+    pub(crate) fn new(threshold_ns: u64) -> Self {
+        Self {
+            threshold_ns,
+            anchor: None,
+            recent_vectors: VecDeque::with_capacity(HANG_WATCHDOG_RECENT_VECTORS),
+        }
+    }
+
+    pub(crate) fn rearm(&mut self) {
+        self.anchor = None;
+    }
+
+    pub(crate) fn record_vector(&mut self, vector: u8) {
+        if self.recent_vectors.len() == HANG_WATCHDOG_RECENT_VECTORS {
+            self.recent_vectors.pop_front();
+        }
+        self.recent_vectors.push_back(vector);
+    }
+
+    pub(crate) fn recent_vectors(&self) -> Vec<u8> {
+        self.recent_vectors.iter().copied().collect()
+    }
+
+    /// Compare `sample` against the last progress point.
+    ///
+    /// Returns the stalled guest time once it reaches the threshold; the watchdog then restarts
+    /// from `sample`, so a guest that stays hung is reported once per threshold.
+    pub(crate) fn check(&mut self, sample: ProgressSample, tsc_hz: u64) -> Option<u64> {
+        let Some(anchor) = self.anchor else {
+            self.anchor = Some(sample);
+            return None;
+        };
+        let progressed = sample.idle
+            || sample.interrupt_injections != anchor.interrupt_injections
+            || sample.port_io != anchor.port_io
+            || sample.linear_rip.abs_diff(anchor.linear_rip) > HANG_WATCHDOG_RIP_WINDOW;
+        if progressed || tsc_hz == 0 {
+            self.anchor = Some(sample);
+            return None;
+        }
+
+        let cycles = sample.tsc.saturating_sub(anchor.tsc);
+        let stalled_ns = u64::try_from(u128::from(cycles) * 1_000_000_000 / u128::from(tsc_hz))
+            .unwrap_or(u64::MAX);
+        if stalled_ns < self.threshold_ns {
+            return None;
+        }
+        self.anchor = Some(sample);
+        Some(stalled_ns)
+    }
+}
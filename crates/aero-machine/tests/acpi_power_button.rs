@@ -0,0 +1,120 @@
+use aero_devices::acpi_pm::{
+    DEFAULT_ACPI_ENABLE, DEFAULT_PM1A_CNT_BLK, DEFAULT_PM1A_EVT_BLK, DEFAULT_SMI_CMD_PORT,
+    PM1_STS_PWRBTN,
+};
+use aero_machine::{GuestPowerState, Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+const PM1_CNT_SCI_EN: u32 = 1 << 0;
+const PM1_CNT_SLP_EN: u32 = 1 << 13;
+
+fn slp_typ(typ: u32) -> u32 {
+    typ << 10
+}
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+#[test]
+fn power_button_raises_sci_once_the_guest_enables_the_fixed_event() {
+    // cli; jmp $
+    let mut m = new_machine(&[0xFA, 0xEB, 0xFE]);
+    let pm = m.acpi_pm().expect("PC platform has ACPI PM");
+
+    // OSPM handshake: ACPI enable via SMI_CMD, then arm PWRBTN_EN.
+    m.io_write(DEFAULT_SMI_CMD_PORT, 1, u32::from(DEFAULT_ACPI_ENABLE));
+    m.io_write(DEFAULT_PM1A_EVT_BLK + 2, 2, u32::from(PM1_STS_PWRBTN));
+    assert!(!pm.borrow().sci_level());
+
+    assert!(m.press_power_button());
+    let sts = m.io_read(DEFAULT_PM1A_EVT_BLK, 2) as u16;
+    assert_eq!(sts & PM1_STS_PWRBTN, PM1_STS_PWRBTN);
+    assert!(pm.borrow().sci_level());
+    // Pressing the button alone does not change the power state; the guest decides.
+    assert_eq!(m.power_state(), GuestPowerState::Running);
+
+    // The guest's SCI handler acknowledges the event (RW1C).
+    m.io_write(DEFAULT_PM1A_EVT_BLK, 2, u32::from(PM1_STS_PWRBTN));
+    assert!(!pm.borrow().sci_level());
+}
+
+#[test]
+fn guest_s5_request_surfaces_as_power_off_until_reset() {
+    // cli; mov dx, PM1a_CNT; mov ax, SLP_EN | SLP_TYP(5) | SCI_EN; out dx, ax; jmp $
+    let value = (PM1_CNT_SLP_EN | slp_typ(5) | PM1_CNT_SCI_EN) as u16;
+    let mut code = vec![0xFA, 0xBA];
+    code.extend_from_slice(&DEFAULT_PM1A_CNT_BLK.to_le_bytes());
+    code.push(0xB8);
+    code.extend_from_slice(&value.to_le_bytes());
+    code.extend_from_slice(&[0xEF, 0xEB, 0xFE]);
+    let mut m = new_machine(&code);
+    assert_eq!(m.power_state(), GuestPowerState::Running);
+
+    match m.run_slice(100_000) {
+        RunExit::PowerOff { executed } => assert!(executed >= 4, "{executed}"),
+        other => panic!("unexpected exit: {other:?}"),
+    }
+    assert_eq!(m.power_state(), GuestPowerState::SoftOff);
+
+    // A powered-off machine does not run again.
+    assert_eq!(m.run_slice(100_000), RunExit::PowerOff { executed: 0 });
+    assert_eq!(m.run_for(1_000_000), RunExit::PowerOff { executed: 0 });
+
+    m.reset();
+    assert_eq!(m.power_state(), GuestPowerState::Running);
+    // The machine runs again after reset (and this guest shuts down again).
+    assert!(matches!(
+        m.run_slice(100_000),
+        RunExit::PowerOff { executed } if executed > 0
+    ));
+}
+
+#[test]
+fn sleep_requests_track_power_state_without_stopping_the_run_loop() {
+    // cli; jmp $
+    let mut m = new_machine(&[0xFA, 0xEB, 0xFE]);
+
+    m.io_write(
+        DEFAULT_PM1A_CNT_BLK,
+        2,
+        PM1_CNT_SLP_EN | slp_typ(3) | PM1_CNT_SCI_EN,
+    );
+    assert_eq!(m.power_state(), GuestPowerState::Sleeping);
+    assert!(matches!(m.run_slice(1000), RunExit::Completed { .. }));
+    assert_eq!(m.power_state(), GuestPowerState::Sleeping);
+
+    m.acpi_wake();
+    assert_eq!(m.power_state(), GuestPowerState::Running);
+
+    // S4 (hibernate) ends in soft-off as well. SLP_EN is edge-triggered, so drop it first.
+    m.io_write(DEFAULT_PM1A_CNT_BLK, 2, PM1_CNT_SCI_EN);
+    m.io_write(
+        DEFAULT_PM1A_CNT_BLK,
+        2,
+        PM1_CNT_SLP_EN | slp_typ(4) | PM1_CNT_SCI_EN,
+    );
+    assert_eq!(m.power_state(), GuestPowerState::SoftOff);
+    assert_eq!(m.run_slice(1000), RunExit::PowerOff { executed: 0 });
+}
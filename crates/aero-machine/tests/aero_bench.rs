@@ -0,0 +1,155 @@
+use aero_machine::{
+    BenchOp, BenchOpStats, Machine, MachineConfig, Resource, RunExit, AERO_BENCH_PORT_BASE,
+    AERO_BENCH_PORT_COUNT, AERO_BENCH_SCRATCH_BYTES, AERO_BENCH_SIGNATURE,
+};
+use pretty_assertions::assert_eq;
+
+const CMD: u16 = AERO_BENCH_PORT_BASE;
+const ADDR_LO: u16 = AERO_BENCH_PORT_BASE + 4;
+const ADDR_HI: u16 = AERO_BENCH_PORT_BASE + 8;
+const RESULT_HI: u16 = AERO_BENCH_PORT_BASE + 0xC;
+
+const NOP_ITERATIONS: u8 = 16;
+
+fn bench_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_aero_bench: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn result(m: &mut Machine) -> u64 {
+    u64::from(m.io_read(CMD, 4)) | (u64::from(m.io_read(RESULT_HI, 4)) << 32)
+}
+
+fn build_bench_boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    let code: &[&[u8]] = &[
+        &[0xFA],                    // cli (keep timer interrupts out of the measured loop)
+        &[0xBA, 0x80, 0x0A],        // mov dx, 0x0A80
+        &[0xB9, NOP_ITERATIONS, 0], // mov cx, NOP_ITERATIONS
+        &[0x31, 0xC0],              // xor ax, ax
+        &[0xEE],                    // .loop: out dx, al (NOP)
+        &[0xE2, 0xFD],              // loop .loop
+        &[0xB0, 0x03],              // mov al, 3
+        &[0xEE],                    // out dx, al (TIMESTAMP)
+        &[0xF4],                    // hlt
+    ];
+    let code = code.concat();
+    sector[..code.len()].copy_from_slice(&code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest never reached HLT");
+}
+
+#[test]
+fn bench_device_is_absent_unless_enabled() {
+    let mut m = Machine::new(MachineConfig::browser_defaults(64 * 1024 * 1024)).unwrap();
+    assert_eq!(m.bench_results(), None);
+    assert!(!m
+        .debug_resource_map()
+        .claims
+        .iter()
+        .any(|c| c.owner == "aero-bench"));
+
+    m.io_write(CMD, 4, BenchOp::Pattern as u32);
+    assert_ne!(m.io_read(CMD, 4), AERO_BENCH_SIGNATURE);
+}
+
+#[test]
+fn pattern_op_returns_signature_and_arg() {
+    let mut m = bench_machine();
+    m.io_write(CMD, 4, BenchOp::Pattern as u32 | (0x12_3456 << 8));
+    assert_eq!(m.io_read(CMD, 4), AERO_BENCH_SIGNATURE);
+    assert_eq!(m.io_read(CMD, 2), AERO_BENCH_SIGNATURE & 0xFFFF);
+    assert_eq!(m.io_read(CMD + 2, 2), AERO_BENCH_SIGNATURE >> 16);
+    assert_eq!(m.io_read(RESULT_HI, 4), 0x12_3456);
+
+    // Unknown ops are counted but leave the result registers alone.
+    m.io_write(CMD, 4, 0xFF);
+    assert_eq!(m.io_read(CMD, 4), AERO_BENCH_SIGNATURE);
+
+    let results = m.bench_results().unwrap();
+    assert_eq!(results.pattern.count, 1);
+    assert_eq!(results.unknown_ops, 1);
+}
+
+#[test]
+fn memcpy_op_reads_guest_buffer_and_clamps_to_scratch() {
+    let mut m = bench_machine();
+    m.write_physical(0x1_0000, &[0x5A; 256]);
+
+    m.io_write(ADDR_LO, 4, 0x1_0000);
+    m.io_write(ADDR_HI, 4, 0);
+    assert_eq!(m.io_read(ADDR_LO, 4), 0x1_0000);
+    m.io_write(CMD, 4, BenchOp::Memcpy as u32 | (256 << 8));
+    assert_eq!(result(&mut m), 256);
+
+    m.io_write(CMD, 4, BenchOp::Memcpy as u32 | (0x10_0000 << 8));
+    assert_eq!(result(&mut m), AERO_BENCH_SCRATCH_BYTES as u64);
+
+    let memcpy = m.bench_results().unwrap().memcpy;
+    assert_eq!(memcpy.count, 2);
+    assert_eq!(memcpy.bytes, 256 + AERO_BENCH_SCRATCH_BYTES as u64);
+}
+
+#[test]
+fn guest_loop_records_per_op_cycle_costs() {
+    let mut m = bench_machine();
+    m.set_disk_image(build_bench_boot_sector().to_vec())
+        .unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+
+    let results = m.bench_results().unwrap();
+    // Deterministic TSC: one cycle per instruction. Each NOP after the first follows `loop`, and
+    // the timestamp follows the final `loop` fall-through and `mov al, 3`.
+    assert_eq!(
+        results.nop,
+        BenchOpStats {
+            count: u64::from(NOP_ITERATIONS),
+            cycles: 2 * u64::from(NOP_ITERATIONS - 1),
+            bytes: 0,
+        }
+    );
+    assert_eq!(results.nop.cycles_per_op(), 1);
+    assert_eq!(results.op(BenchOp::Timestamp).count, 1);
+    assert_eq!(results.timestamp.cycles, 3);
+    assert_ne!(result(&mut m), 0, "timestamp op returns guest time");
+
+    m.clear_bench_results();
+    assert_eq!(m.bench_results().unwrap().nop.count, 0);
+}
+
+#[test]
+fn bench_ports_are_claimed_without_acpi_mismatches() {
+    let mut m = Machine::new(MachineConfig {
+        enable_aero_bench: true,
+        ..MachineConfig::browser_defaults(64 * 1024 * 1024)
+    })
+    .unwrap();
+    let map = m.debug_resource_map();
+    assert!(map.claims.iter().any(|c| c.owner == "aero-bench"
+        && c.resource
+            == Resource::Io {
+                start: AERO_BENCH_PORT_BASE,
+                end: AERO_BENCH_PORT_BASE + AERO_BENCH_PORT_COUNT - 1,
+            }));
+    assert_eq!(map.mismatches, vec![]);
+}
@@ -85,7 +85,7 @@ fn aerogpu_backend_scanout_is_presented_via_machine_display_present() {
             [1, 2, 3, 255],
         )));
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
 
     let fb = m.display_framebuffer();
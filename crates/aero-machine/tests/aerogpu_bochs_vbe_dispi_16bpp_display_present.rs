@@ -56,7 +56,7 @@ fn aerogpu_bochs_vbe_dispi_16bpp_display_present_respects_offsets_and_stride() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (2, 2));
     // For 0x8543 (RGB565): r=0b10000 -> 0x84, g=0b101010 -> 0xAA, b=0b00011 -> 0x18.
@@ -110,7 +110,7 @@ fn aerogpu_bochs_vbe_dispi_32bpp_display_present_respects_offsets_and_stride() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(m.display_framebuffer()[0], 0xFF18_AA84);
@@ -141,7 +141,7 @@ fn aerogpu_bochs_vbe_dispi_oversized_dimensions_do_not_oom_or_panic() {
     m.io_write(0x01CE, 2, 0x0004);
     m.io_write(0x01CF, 2, 0x0041); // enable + lfb
 
-    m.display_present();
+    m.display_present(true);
 
     // If the VBE mode is rejected, the machine falls back to BIOS text mode (80x25 -> 720x400).
     assert_eq!(m.display_resolution(), (720, 400));
@@ -49,7 +49,7 @@ fn aerogpu_bochs_vbe_dispi_8bpp_display_present_uses_dac_palette() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     // RGBA8888 little-endian u32: [R, G, B, A].
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
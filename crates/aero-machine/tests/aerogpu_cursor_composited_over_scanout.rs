@@ -168,7 +168,7 @@ fn aerogpu_cursor_is_composited_over_scanout() {
     );
 
     // Present and validate.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (scanout_w, scanout_h));
     let fb = m.display_framebuffer();
     assert_eq!(fb.len(), (scanout_w * scanout_h) as usize);
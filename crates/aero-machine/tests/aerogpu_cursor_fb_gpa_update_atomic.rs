@@ -92,7 +92,7 @@ fn aerogpu_cursor_fb_gpa_update_is_atomic_at_hi_commit() {
     );
     m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_ENABLE), 1);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(
         m.display_framebuffer(),
@@ -106,7 +106,7 @@ fn aerogpu_cursor_fb_gpa_update_is_atomic_at_hi_commit() {
         bar0 + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_FB_GPA_LO),
         cursor_b as u32,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(
         m.display_framebuffer(),
@@ -119,7 +119,7 @@ fn aerogpu_cursor_fb_gpa_update_is_atomic_at_hi_commit() {
         bar0 + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_FB_GPA_HI),
         (cursor_b >> 32) as u32,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(m.display_framebuffer(), &[0xFF00_FF00]); // green
 }
@@ -167,7 +167,7 @@ fn aerogpu_cursor_readback_is_capped_to_avoid_unbounded_allocations() {
     );
 
     // Present and validate scanout is still shown, with cursor ignored due to cap.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (scanout_w, scanout_h));
 
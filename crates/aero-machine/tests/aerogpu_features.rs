@@ -65,11 +65,15 @@ fn aerogpu_features_lo_hi_match_implemented_capabilities() {
     // - Fence-page writeback
     // - Cursor overlay
     // - Error reporting registers
+    // - Runtime vblank period changes (`Machine::aerogpu_set_vblank_rate`)
+    // - MSI-X fence/vblank/error vectors
     let expected = pci::AEROGPU_FEATURE_SCANOUT
         | pci::AEROGPU_FEATURE_CURSOR
         | pci::AEROGPU_FEATURE_VBLANK
         | pci::AEROGPU_FEATURE_FENCE_PAGE
         | pci::AEROGPU_FEATURE_ERROR_INFO
+        | pci::AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE
+        | pci::AEROGPU_FEATURE_MSIX
         // Transfer/copy opcodes are feature-gated in ABI 1.1+.
         | if pci::AEROGPU_ABI_MINOR >= 1 {
             pci::AEROGPU_FEATURE_TRANSFER
@@ -64,7 +64,7 @@ fn aerogpu_legacy_vbe_lfb_banked_window_maps_into_vram() {
     let banked_window = 0xA0000u64 + bank_off;
     m.write_physical_u32(banked_window, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 300));
     assert_eq!(m.display_framebuffer()[(y as usize) * 64], 0xFF00_00FF);
 }
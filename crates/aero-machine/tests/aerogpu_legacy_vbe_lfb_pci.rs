@@ -48,7 +48,7 @@ fn aerogpu_legacy_vbe_lfb_is_reachable_via_pci_mmio_router() {
     // Write a red pixel at (0,0) in packed 32bpp BGRX via *machine memory*.
     m.write_physical_u32(base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -96,7 +96,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -143,7 +143,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -205,7 +205,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -223,7 +223,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         bar0_base + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_ENABLE),
         0,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -236,7 +236,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         bar0_base + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_ENABLE),
         1,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -276,7 +276,7 @@ fn aerogpu_mmio_scanout_and_cursor_present() {
         bar0_base + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_ENABLE),
         1,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -375,7 +375,7 @@ fn aerogpu_mmio_scanout_and_cursor_present_from_bar1_vram() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -422,7 +422,7 @@ fn aerogpu_mmio_scanout_and_cursor_present_from_bar1_vram() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -522,7 +522,7 @@ fn aerogpu_mmio_scanout_rgba_present() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 2));
     assert_eq!(
         m.display_framebuffer(),
@@ -655,7 +655,7 @@ fn aerogpu_mmio_cursor_bgra_present() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (2, 1));
     assert_eq!(m.display_framebuffer(), &[0xFF00_FF00, 0xFF00_00FF]);
 }
@@ -72,13 +72,13 @@ fn aerogpu_mode13h_render_uses_vga_dac_palette_and_pel_mask() {
 
     // With PEL mask=0xFF, index 1 should show as red.
     m.io_write(0x3C6, 1, 0xFF);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 
     // With PEL mask=0, index 1 should be masked to 0 and show as green.
     m.io_write(0x3C6, 1, 0x00);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_framebuffer()[0], 0xFF00_FF00);
     assert_eq!(m.io_read(0x3C6, 1) as u8, 0x00);
 }
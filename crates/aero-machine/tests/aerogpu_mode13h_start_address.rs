@@ -67,7 +67,7 @@ fn aerogpu_mode13h_respects_crtc_start_address_and_byte_mode() {
     m.io_write(0x3D4, 1, 0x0D);
     m.io_write(0x3D5, 1, 0x01);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     // VGA palette entry 2 is EGA green (0x00,0xAA,0x00).
     assert_eq!(m.display_framebuffer()[0], 0xFF00_AA00);
@@ -76,7 +76,7 @@ fn aerogpu_mode13h_respects_crtc_start_address_and_byte_mode() {
     m.io_write(0x3D4, 1, 0x17);
     m.io_write(0x3D5, 1, 0x40);
 
-    m.display_present();
+    m.display_present(true);
     // VGA palette entry 1 is EGA blue (0x00,0x00,0xAA).
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_0000);
 
@@ -91,7 +91,7 @@ fn aerogpu_mode13h_respects_crtc_start_address_and_byte_mode() {
     m2.restore_snapshot_bytes(&snap).unwrap();
 
     assert_eq!(m2.active_scanout_source(), ScanoutSource::LegacyVga);
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.display_resolution(), (320, 200));
     assert_eq!(m2.display_framebuffer()[0], 0xFFAA_0000);
 
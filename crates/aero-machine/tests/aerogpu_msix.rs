@@ -0,0 +1,292 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::msix::PCI_CAP_ID_MSIX;
+use aero_devices::pci::{profile, PciBdf, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig};
+use aero_platform::interrupts::{
+    InterruptController as PlatformInterruptController, PlatformInterruptMode,
+};
+use aero_protocol::aerogpu::{aerogpu_pci as pci, aerogpu_ring as ring};
+use pretty_assertions::{assert_eq, assert_ne};
+
+const FENCE_VECTOR: u8 = 0x61;
+const VBLANK_VECTOR: u8 = 0x62;
+const ERROR_VECTOR: u8 = 0x63;
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1f) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xfc)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_read(PCI_CFG_DATA_PORT + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_write(PCI_CFG_DATA_PORT + (offset & 3), size, value);
+}
+
+fn find_capability(m: &mut Machine, bdf: PciBdf, cap_id: u8) -> Option<u16> {
+    let mut ptr = cfg_read(m, bdf, 0x34, 1) as u8;
+    for _ in 0..64 {
+        if ptr == 0 {
+            return None;
+        }
+        let id = cfg_read(m, bdf, u16::from(ptr), 1) as u8;
+        if id == cap_id {
+            return Some(u16::from(ptr));
+        }
+        ptr = cfg_read(m, bdf, u16::from(ptr) + 1, 1) as u8;
+    }
+    None
+}
+
+fn new_machine() -> Machine {
+    let m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        // Keep the machine minimal/deterministic for interrupt assertions.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.platform_interrupts()
+        .expect("pc platform enabled")
+        .borrow_mut()
+        .set_mode(PlatformInterruptMode::Apic);
+    m
+}
+
+/// Enable MEM + BME and MSI-X, and program the fence/vblank/error table entries. Returns
+/// `(bar0, msix_cap)`.
+fn enable_msix(m: &mut Machine) -> (u64, u16) {
+    let bdf = profile::AEROGPU.bdf;
+    let cmd = cfg_read(m, bdf, 0x04, 2) as u16;
+    cfg_write(m, bdf, 0x04, 2, u32::from(cmd | (1 << 1) | (1 << 2)));
+    let bar0 = u64::from(cfg_read(m, bdf, 0x10, 4) & !0xf);
+    assert_ne!(
+        bar0, 0,
+        "expected AeroGPU BAR0 to be assigned during BIOS POST"
+    );
+
+    let cap = find_capability(m, bdf, PCI_CAP_ID_MSIX).expect("AeroGPU should expose MSI-X");
+    let ctrl = cfg_read(m, bdf, cap + 0x02, 2) as u16;
+    assert_eq!(
+        usize::from(ctrl & 0x07ff) + 1,
+        3,
+        "fence/vblank/error vectors"
+    );
+    cfg_write(m, bdf, cap + 0x02, 2, u32::from(ctrl | (1 << 15)));
+
+    let table = cfg_read(m, bdf, cap + 0x04, 4);
+    assert_eq!(table & 0x7, 0, "MSI-X table must live in BAR0 (BIR=0)");
+    assert_eq!(table, pci::AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES);
+    for (index, vector) in [
+        (pci::AEROGPU_MSIX_VECTOR_FENCE, FENCE_VECTOR),
+        (pci::AEROGPU_MSIX_VECTOR_VBLANK, VBLANK_VECTOR),
+        (pci::AEROGPU_MSIX_VECTOR_ERROR, ERROR_VECTOR),
+    ] {
+        let entry = bar0 + u64::from(table) + u64::from(index) * 16;
+        m.write_physical_u32(entry, 0xfee0_0000);
+        m.write_physical_u32(entry + 0x4, 0);
+        m.write_physical_u32(entry + 0x8, u32::from(vector));
+        m.write_physical_u32(entry + 0xc, 0); // unmasked
+    }
+    (bar0, cap)
+}
+
+/// Submit one descriptor signalling `fence` and ring the doorbell.
+fn submit_fence(m: &mut Machine, bar0: u64, fence: u64) {
+    let ring_gpa = 0x10000u64;
+    let entry_count = 8u32;
+    let entry_stride_bytes = ring::AerogpuSubmitDesc::SIZE_BYTES as u32;
+    let ring_size_bytes =
+        ring::AerogpuRingHeader::SIZE_BYTES as u32 + entry_count * entry_stride_bytes;
+
+    m.write_physical_u32(ring_gpa, ring::AEROGPU_RING_MAGIC);
+    m.write_physical_u32(ring_gpa + 4, pci::AEROGPU_ABI_VERSION_U32);
+    m.write_physical_u32(ring_gpa + 8, ring_size_bytes);
+    m.write_physical_u32(ring_gpa + 12, entry_count);
+    m.write_physical_u32(ring_gpa + 16, entry_stride_bytes);
+    m.write_physical_u32(ring_gpa + 20, 0); // flags
+    m.write_physical_u32(ring_gpa + 24, 0); // head
+    m.write_physical_u32(ring_gpa + 28, 1); // tail
+
+    let desc_gpa = ring_gpa + ring::AerogpuRingHeader::SIZE_BYTES as u64;
+    m.write_physical(desc_gpa, &[0u8; ring::AerogpuSubmitDesc::SIZE_BYTES]);
+    m.write_physical_u32(desc_gpa, entry_stride_bytes); // desc_size_bytes
+    m.write_physical_u32(desc_gpa + 12, ring::AEROGPU_ENGINE_0);
+    m.write_physical_u64(desc_gpa + 48, fence);
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_RING_GPA_LO),
+        ring_gpa as u32,
+    );
+    m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_RING_GPA_HI), 0);
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_RING_SIZE_BYTES),
+        ring_size_bytes,
+    );
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_RING_CONTROL),
+        pci::AEROGPU_RING_CONTROL_ENABLE,
+    );
+    m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_DOORBELL), 1);
+}
+
+fn pending_vector(m: &Machine) -> Option<u8> {
+    let interrupts = m.platform_interrupts().unwrap();
+    let ints = interrupts.borrow();
+    PlatformInterruptController::get_pending(&*ints)
+}
+
+fn aerogpu_irq_level(m: &Machine) -> bool {
+    m.aerogpu_mmio().unwrap().borrow().irq_level()
+}
+
+#[test]
+fn aerogpu_msix_fence_vector_delivers_to_lapic_without_intx() {
+    let mut m = new_machine();
+    let (bar0, _) = enable_msix(&mut m);
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_FENCE,
+    );
+    submit_fence(&mut m, bar0, 42);
+
+    assert_eq!(pending_vector(&m), None);
+    m.process_aerogpu();
+    assert_eq!(pending_vector(&m), Some(FENCE_VECTOR));
+
+    // IRQ_STATUS still latches the cause for the driver's ISR, but INTx stays deasserted.
+    assert_eq!(
+        m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_STATUS)),
+        pci::AEROGPU_IRQ_FENCE
+    );
+    assert!(!aerogpu_irq_level(&m));
+}
+
+#[test]
+fn aerogpu_msix_vblank_uses_its_own_vector() {
+    let mut m = new_machine();
+    let (bar0, _) = enable_msix(&mut m);
+
+    m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE), 1);
+    let period_ns = u64::from(
+        m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS)),
+    );
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_SCANOUT_VBLANK,
+    );
+
+    m.tick_platform(period_ns);
+    m.process_aerogpu();
+    assert_eq!(pending_vector(&m), Some(VBLANK_VECTOR));
+    assert!(!aerogpu_irq_level(&m));
+}
+
+#[test]
+fn aerogpu_msix_masked_vector_is_pending_until_unmasked() {
+    let mut m = new_machine();
+    let (bar0, _) = enable_msix(&mut m);
+
+    let entry = bar0
+        + u64::from(pci::AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES)
+        + u64::from(pci::AEROGPU_MSIX_VECTOR_FENCE) * 16;
+    m.write_physical_u32(entry + 0xc, 1); // mask
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_FENCE,
+    );
+    submit_fence(&mut m, bar0, 7);
+    m.process_aerogpu();
+
+    assert_eq!(pending_vector(&m), None);
+    let pba = bar0 + u64::from(pci::AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES);
+    assert_eq!(
+        m.read_physical_u32(pba),
+        1 << pci::AEROGPU_MSIX_VECTOR_FENCE
+    );
+    assert!(
+        !aerogpu_irq_level(&m),
+        "masked MSI-X must not fall back to INTx"
+    );
+
+    m.write_physical_u32(entry + 0xc, 0); // unmask
+    assert_eq!(pending_vector(&m), Some(FENCE_VECTOR));
+    assert_eq!(m.read_physical_u32(pba), 0);
+}
+
+#[test]
+fn aerogpu_falls_back_to_intx_when_msix_is_disabled() {
+    let mut m = new_machine();
+    let (bar0, cap) = enable_msix(&mut m);
+
+    let bdf = profile::AEROGPU.bdf;
+    let ctrl = cfg_read(&mut m, bdf, cap + 0x02, 2) as u16;
+    cfg_write(&mut m, bdf, cap + 0x02, 2, u32::from(ctrl & !(1 << 15)));
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_FENCE,
+    );
+    submit_fence(&mut m, bar0, 9);
+    m.process_aerogpu();
+
+    assert_ne!(pending_vector(&m), Some(FENCE_VECTOR));
+    assert!(aerogpu_irq_level(&m));
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ACK),
+        pci::AEROGPU_IRQ_FENCE,
+    );
+    assert!(!aerogpu_irq_level(&m));
+}
+
+#[test]
+fn aerogpu_msix_table_survives_snapshot_restore() {
+    let mut src = new_machine();
+    let (bar0, _) = enable_msix(&mut src);
+    let table = bar0 + u64::from(pci::AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES);
+    let error_entry = table + u64::from(pci::AEROGPU_MSIX_VECTOR_ERROR) * 16;
+    src.write_physical_u32(error_entry + 0xc, 1); // mask the error vector
+
+    let snapshot = src.take_snapshot_full().unwrap();
+    let mut restored = new_machine();
+    restored.restore_snapshot_bytes(&snapshot).unwrap();
+    restored
+        .platform_interrupts()
+        .unwrap()
+        .borrow_mut()
+        .set_mode(PlatformInterruptMode::Apic);
+
+    for off in (0..3 * 16).step_by(4) {
+        assert_eq!(
+            restored.read_physical_u32(table + off),
+            src.read_physical_u32(table + off),
+            "MSI-X table dword at {off:#x}"
+        );
+    }
+
+    // The restored device still signals through the restored table.
+    restored.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_FENCE,
+    );
+    submit_fence(&mut restored, bar0, 11);
+    restored.process_aerogpu();
+    assert_eq!(pending_vector(&restored), Some(FENCE_VECTOR));
+}
@@ -91,7 +91,7 @@ fn aerogpu_bme_toggle_gates_ring_dma_and_scanout_reads() {
     );
     m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE), 1);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
 
@@ -212,7 +212,7 @@ fn aerogpu_bme_toggle_gates_ring_dma_and_scanout_reads() {
     );
 
     // Scanout reads must also be gated by BME.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
 
@@ -224,7 +224,7 @@ fn aerogpu_bme_toggle_gates_ring_dma_and_scanout_reads() {
     assert_eq!(m.read_physical_u32(ring_gpa + 24), 2);
     assert_eq!(m.read_physical_u64(fence_gpa + 8), signal_fence1);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
 }
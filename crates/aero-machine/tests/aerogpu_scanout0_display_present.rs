@@ -130,7 +130,7 @@ fn aerogpu_scanout0_display_present_reads_guest_framebuffer() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], u32::from_le_bytes(rgba));
@@ -215,7 +215,7 @@ fn aerogpu_scanout0_display_present_prefers_backend_scanout_when_available() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], u32::from_le_bytes(backend_rgba));
@@ -68,7 +68,7 @@ fn aerogpu_scanout_claim_defers_until_fb_gpa_hi_is_written() {
 
     // Must *not* hand off to WDDM until FB_GPA_HI is written.
     assert_ne!(m.active_scanout_source(), ScanoutSource::Wddm);
-    m.display_present();
+    m.display_present(true);
     assert_ne!(
         m.display_resolution(),
         (1, 1),
@@ -82,7 +82,7 @@ fn aerogpu_scanout_claim_defers_until_fb_gpa_hi_is_written() {
     );
 
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(m.display_framebuffer(), &[0xFFAA_BBCC]);
 }
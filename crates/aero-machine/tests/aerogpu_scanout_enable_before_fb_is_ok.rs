@@ -35,7 +35,7 @@ fn aerogpu_scanout_enable_before_fb_is_ok() {
     // Seed a visible legacy VGA text cell so we can detect accidental handoff to a blank WDDM
     // scanout.
     m.write_physical_u16(0xB8000, 0x1F41); // 'A' with bright attribute
-    m.display_present();
+    m.display_present(true);
 
     let legacy_res = m.display_resolution();
     assert_eq!(legacy_res, (720, 400));
@@ -95,7 +95,7 @@ fn aerogpu_scanout_enable_before_fb_is_ok() {
     m.process_aerogpu();
 
     // Scanout must *not* hand off to WDDM yet, since FB_GPA=0 is not a valid config.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), legacy_res);
     assert_eq!(scanout_state.snapshot().source, SCANOUT_SOURCE_LEGACY_TEXT);
 
@@ -135,7 +135,7 @@ fn aerogpu_scanout_enable_before_fb_is_ok() {
     // Run the device tick to publish the now-valid scanout config.
     m.process_aerogpu();
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (w, h));
     assert_eq!(scanout_state.snapshot().source, SCANOUT_SOURCE_WDDM);
@@ -150,7 +150,7 @@ fn aerogpu_scanout_enable_before_fb_is_ok() {
 
     // Once WDDM is active, legacy VGA writes must not steal scanout back.
     m.write_physical_u16(0xB8000, 0x2E42); // 'B'
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (w, h));
     assert_eq!(m.display_framebuffer(), expected.as_slice());
 
@@ -163,7 +163,7 @@ fn aerogpu_scanout_enable_before_fb_is_ok() {
         0,
     );
     m.process_aerogpu();
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
     let snap = scanout_state.snapshot();
@@ -65,7 +65,7 @@ fn aerogpu_scanout_fb_gpa_update_is_atomic_at_hi_commit() {
     m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE), 1);
 
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(m.display_framebuffer(), &[0xFF00_00FF]); // red
 
@@ -74,7 +74,7 @@ fn aerogpu_scanout_fb_gpa_update_is_atomic_at_hi_commit() {
         bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_LO),
         fb_b as u32,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(
         m.display_framebuffer(),
@@ -87,7 +87,7 @@ fn aerogpu_scanout_fb_gpa_update_is_atomic_at_hi_commit() {
         bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_HI),
         (fb_b >> 32) as u32,
     );
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1, 1));
     assert_eq!(m.display_framebuffer(), &[0xFF00_FF00]); // green
 }
@@ -128,7 +128,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
     m.write_physical_u8(0xB8000, b'A');
     m.write_physical_u8(0xB8001, 0x1F);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
-    m.display_present();
+    m.display_present(true);
     let legacy_text_res = m.display_resolution();
     assert_ne!(legacy_text_res, (0, 0));
 
@@ -226,7 +226,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
         cfg.set_command(cfg.command() | (1 << 2));
     }
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
@@ -244,7 +244,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
     m.write_physical_u8(WAIT_FLAG_PADDR, 0x02);
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
@@ -255,7 +255,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
     m.write_physical_u16(BDA_CURSOR_SHAPE_ADDR, 0x2000);
     m.write_physical_u8(0xB8000, b'Z');
     m.write_physical_u8(0xB8001, 0x1F);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
@@ -268,7 +268,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
         0,
     );
     m.process_aerogpu();
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -279,7 +279,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
     m.write_physical_u16(BDA_CURSOR_SHAPE_ADDR, 0x2000);
     m.write_physical_u8(0xB8000, b'Y');
     m.write_physical_u8(0xB8001, 0x1F);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -292,7 +292,7 @@ fn aerogpu_scanout_handoff_to_wddm_blocks_legacy_int10_steal() {
     m.write_physical_u8(0xB8000, b'R');
     m.write_physical_u8(0xB8001, 0x1F);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), legacy_text_res);
     assert!(!m.display_framebuffer().is_empty());
 }
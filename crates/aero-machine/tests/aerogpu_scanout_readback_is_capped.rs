@@ -26,7 +26,7 @@ fn aerogpu_scanout_readback_is_capped_to_avoid_unbounded_allocations() {
     // Establish a known legacy baseline so we can verify WDDM ownership stays authoritative once
     // claimed (even if scanout cannot be presented due to host-side readback limits).
     m.write_physical_u16(0xB8000, 0x1F41); // 'A'
-    m.display_present();
+    m.display_present(true);
     let legacy_res = m.display_resolution();
     assert_eq!(legacy_res, (720, 400));
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
@@ -81,7 +81,7 @@ fn aerogpu_scanout_readback_is_capped_to_avoid_unbounded_allocations() {
 
     // The scanout is valid enough to claim WDDM ownership, but the host readback path must reject
     // it to avoid allocating an enormous temporary buffer.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -93,7 +93,7 @@ fn aerogpu_scanout_readback_is_capped_to_avoid_unbounded_allocations() {
         0,
     );
     m.process_aerogpu();
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -101,7 +101,7 @@ fn aerogpu_scanout_readback_is_capped_to_avoid_unbounded_allocations() {
     // Reset returns scanout ownership to legacy.
     m.reset();
     m.write_physical_u16(0xB8000, 0x1F41); // 'A'
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
     assert_eq!(m.display_resolution(), legacy_res);
 }
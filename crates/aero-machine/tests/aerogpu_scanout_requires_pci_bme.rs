@@ -28,7 +28,7 @@ fn aerogpu_scanout_requires_pci_bus_master_enable_for_host_reads() {
     // Seed a visible legacy VGA text cell so we can detect accidental fallback when the guest has
     // already claimed WDDM scanout.
     m.write_physical_u16(0xB8000, 0x1F41); // 'A' with bright attribute
-    m.display_present();
+    m.display_present(true);
     let legacy_res = m.display_resolution();
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
     assert_eq!(legacy_res, (720, 400));
@@ -88,7 +88,7 @@ fn aerogpu_scanout_requires_pci_bus_master_enable_for_host_reads() {
     // ---------------------------------------------------------------------
     // 1) With PCI COMMAND.BME=0, host-side scanout reads must be gated off.
     // ---------------------------------------------------------------------
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -106,7 +106,7 @@ fn aerogpu_scanout_requires_pci_bus_master_enable_for_host_reads() {
         cfg.set_command(cfg.command() | (1 << 2)); // COMMAND.BME
     }
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (w, h));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
@@ -118,7 +118,7 @@ fn aerogpu_scanout_requires_pci_bus_master_enable_for_host_reads() {
         0,
     );
     m.process_aerogpu();
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
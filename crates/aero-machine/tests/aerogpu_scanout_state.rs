@@ -249,7 +249,7 @@ fn aerogpu_scanout_state_wddm_mismatch_falls_back_to_legacy_text_for_8bpp_vbe()
     run_until_halt(&mut m, 200);
 
     // Ensure the BIOS VBE mode is set (so this is *not* the "None => legacy text" fallback).
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
 
     // Simulate a shared scanout descriptor stuck in WDDM mode (e.g. after a reset/restore mismatch)
@@ -262,6 +262,8 @@ fn aerogpu_scanout_state_wddm_mismatch_falls_back_to_legacy_text_for_8bpp_vbe()
         height: 1,
         pitch_bytes: 4,
         format: SCANOUT_FORMAT_B8G8R8X8,
+        slot0_offset: 0,
+        slot1_offset: 0,
     });
 
     let gen_before = scanout_state.snapshot().generation;
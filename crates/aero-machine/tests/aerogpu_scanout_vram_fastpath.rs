@@ -177,7 +177,7 @@ fn aerogpu_scanout_reads_from_bar1_vram_without_mmio_reads_during_present() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (800, 600));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
@@ -285,7 +285,7 @@ fn aerogpu_vbe_lfb_fastpath_honors_panning_and_stride() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
 
     // The mode is 1024x768x32bpp (VBE 0x118).
     assert_eq!(m.display_resolution(), (1024, 768));
@@ -364,7 +364,7 @@ fn aerogpu_vbe_8bpp_vram_fastpath_uses_dac_palette_and_avoids_mmio_reads() {
         .aerogpu_bar1_mmio_read_count()
         .expect("AeroGPU should expose a BAR1 MMIO read counter");
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (800, 600));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
@@ -83,7 +83,7 @@ fn aerogpu_snapshot_preserves_mode13h_scanout() {
     m.reset();
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyVga);
     assert_eq!(m.display_resolution(), (320, 200));
     let pixel_before = m.display_framebuffer()[(y as usize) * 320 + (x as usize)];
@@ -96,7 +96,7 @@ fn aerogpu_snapshot_preserves_mode13h_scanout() {
     m2.reset();
     m2.restore_snapshot_bytes(&snap).unwrap();
 
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.active_scanout_source(), ScanoutSource::LegacyVga);
     assert_eq!(m2.display_resolution(), (320, 200));
     let pixel_after = m2.display_framebuffer()[(y as usize) * 320 + (x as usize)];
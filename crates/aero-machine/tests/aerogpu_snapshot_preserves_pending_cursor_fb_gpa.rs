@@ -92,7 +92,7 @@ fn aerogpu_snapshot_preserves_pending_cursor_fb_gpa_lo_until_hi_commit() {
     );
     vm.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_ENABLE), 1);
 
-    vm.display_present();
+    vm.display_present(true);
     assert_eq!(vm.display_framebuffer(), &[0xFFFF_0000]); // blue cursor over red scanout
 
     // Start a cursor FB_GPA update by writing only LO.
@@ -107,7 +107,7 @@ fn aerogpu_snapshot_preserves_pending_cursor_fb_gpa_lo_until_hi_commit() {
     );
 
     // Cursor base should remain stable until the HI write commits.
-    vm.display_present();
+    vm.display_present(true);
     assert_eq!(
         vm.display_framebuffer(),
         &[0xFFFF_0000],
@@ -134,7 +134,7 @@ fn aerogpu_snapshot_preserves_pending_cursor_fb_gpa_lo_until_hi_commit() {
         "pending LO write should survive snapshot restore so a subsequent HI write commits correctly"
     );
 
-    restored.display_present();
+    restored.display_present(true);
     assert_eq!(
         restored.display_framebuffer(),
         &[0xFFFF_0000],
@@ -148,6 +148,6 @@ fn aerogpu_snapshot_preserves_pending_cursor_fb_gpa_lo_until_hi_commit() {
         (cursor_b >> 32) as u32,
     );
 
-    restored.display_present();
+    restored.display_present(true);
     assert_eq!(restored.display_framebuffer(), &[0xFF00_FF00]); // green
 }
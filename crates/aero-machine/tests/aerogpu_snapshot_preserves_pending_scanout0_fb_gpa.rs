@@ -64,7 +64,7 @@ fn aerogpu_snapshot_preserves_pending_scanout0_fb_gpa_lo_until_hi_commit() {
     vm.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE), 1);
 
     assert_eq!(vm.active_scanout_source(), ScanoutSource::Wddm);
-    vm.display_present();
+    vm.display_present(true);
     assert_eq!(vm.display_framebuffer(), &[0xFF00_00FF]); // red
 
     // Start an FB_GPA update by writing only LO.
@@ -105,6 +105,6 @@ fn aerogpu_snapshot_preserves_pending_scanout0_fb_gpa_lo_until_hi_commit() {
         (fb_b >> 32) as u32,
     );
 
-    restored.display_present();
+    restored.display_present(true);
     assert_eq!(restored.display_framebuffer(), &[0xFF00_FF00]); // green
 }
@@ -45,7 +45,7 @@ fn aerogpu_snapshot_preserves_vbe_dispi_register_file_and_scanout() {
     let base = m.vbe_lfb_base();
     m.write_physical_u32(base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     let expected_fb: Vec<u32> = m.display_framebuffer().to_vec();
 
@@ -65,7 +65,7 @@ fn aerogpu_snapshot_preserves_vbe_dispi_register_file_and_scanout() {
     restored.io_write(0x01CE, 2, 0x0004);
     assert_eq!(restored.io_read(0x01CF, 2) as u16, 0x0041);
 
-    restored.display_present();
+    restored.display_present(true);
     assert_eq!(restored.display_resolution(), (64, 64));
     assert_eq!(restored.display_framebuffer(), expected_fb.as_slice());
 }
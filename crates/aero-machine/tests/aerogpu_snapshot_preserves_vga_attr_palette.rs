@@ -54,7 +54,7 @@ fn aerogpu_snapshot_preserves_vga_attribute_controller_palette_mapping() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x10); // bg=1, fg=0
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (720, 400));
     // RGBA8888 little-endian u32: [R, G, B, A].
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
@@ -67,7 +67,7 @@ fn aerogpu_snapshot_preserves_vga_attribute_controller_palette_mapping() {
 
     // The palette mapping should survive snapshot/restore (text scanout uses Attribute Controller
     // regs).
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.display_resolution(), (720, 400));
     assert_eq!(m2.display_framebuffer()[0], 0xFF00_00FF);
 
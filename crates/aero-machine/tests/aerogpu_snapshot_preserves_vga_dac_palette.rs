@@ -80,7 +80,7 @@ fn aerogpu_snapshot_preserves_vga_dac_palette_and_pel_mask() {
     let lfb_base = m.vbe_lfb_base();
     m.write_physical_u8(lfb_base, 1);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_FF00);
 
@@ -92,7 +92,7 @@ fn aerogpu_snapshot_preserves_vga_dac_palette_and_pel_mask() {
     m2.reset();
     m2.restore_snapshot_bytes(&snap).unwrap();
 
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.display_resolution(), (1024, 768));
     assert_eq!(m2.display_framebuffer()[0], 0xFF00_FF00);
     assert_eq!(m2.io_read(0x3C6, 1) as u8, 0x00);
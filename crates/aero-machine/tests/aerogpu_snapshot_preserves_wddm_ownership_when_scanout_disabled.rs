@@ -32,7 +32,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
     m.write_physical_u8(0xB8000, b'A');
     m.write_physical_u8(0xB8001, 0x1F);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyText);
-    m.display_present();
+    m.display_present(true);
     let legacy_res = m.display_resolution();
     assert_ne!(legacy_res, (0, 0));
     assert!(!m.display_framebuffer().is_empty());
@@ -97,7 +97,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
         1,
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (width, height));
     assert_eq!(m.display_framebuffer()[0], 0xFFAA_BBCC);
@@ -108,7 +108,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
         0,
     );
     m.process_aerogpu();
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m.display_resolution(), (0, 0));
     assert!(m.display_framebuffer().is_empty());
@@ -120,7 +120,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
     m2.restore_snapshot_bytes(&snap).unwrap();
 
     assert_eq!(m2.active_scanout_source(), ScanoutSource::Wddm);
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.display_resolution(), (0, 0));
     assert!(m2.display_framebuffer().is_empty());
 
@@ -130,7 +130,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
     m2.write_physical_u16(BDA_CURSOR_SHAPE_ADDR, 0x2000);
     m2.write_physical_u8(0xB8000, b'Z');
     m2.write_physical_u8(0xB8001, 0x1F);
-    m2.display_present();
+    m2.display_present(true);
     assert_eq!(m2.active_scanout_source(), ScanoutSource::Wddm);
     assert_eq!(m2.display_resolution(), (0, 0));
     assert!(m2.display_framebuffer().is_empty());
@@ -143,7 +143,7 @@ fn aerogpu_snapshot_preserves_wddm_ownership_when_scanout_disabled() {
     m2.write_physical_u8(0xB8000, b'R');
     m2.write_physical_u8(0xB8001, 0x1F);
     assert_eq!(m2.active_scanout_source(), ScanoutSource::LegacyText);
-    m2.display_present();
+    m2.display_present(true);
     assert_ne!(m2.display_resolution(), (0, 0));
     assert!(!m2.display_framebuffer().is_empty());
 }
@@ -111,7 +111,7 @@ fn aerogpu_snapshot_roundtrip_restores_bar0_regs_and_vram() {
         1,
     );
 
-    vm.display_present();
+    vm.display_present(true);
     assert_eq!(vm.display_resolution(), (width, height));
     let fb_before = vm.display_framebuffer().to_vec();
     assert_eq!(fb_before[0], 0xFF00_00FF);
@@ -129,7 +129,7 @@ fn aerogpu_snapshot_roundtrip_restores_bar0_regs_and_vram() {
     let after_pattern = vm2.read_physical_bytes(legacy_base, pattern.len());
     assert_eq!(after_pattern, pattern);
 
-    vm2.display_present();
+    vm2.display_present(true);
     assert_eq!(vm2.display_resolution(), (width, height));
     let fb_after = vm2.display_framebuffer();
     assert_eq!(fb_after[0..4], fb_before[0..4]);
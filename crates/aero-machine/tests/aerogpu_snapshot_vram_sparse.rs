@@ -94,7 +94,7 @@ fn aerogpu_snapshot_vram_sparse_page_list_tracks_only_non_zero_pages() {
     // 3) Take another snapshot and ensure:
     //    - the page count increases by exactly 1
     //    - the new page's payload contains our bytes
-    //    - the snapshot payload stays small (i.e. doesn't dump the full 16MiB VRAM prefix)
+    //    - the snapshot payload stays small (i.e. doesn't dump the full VRAM aperture)
 
     fn extract_aerogpu_device_entry(snapshot: &[u8]) -> (u16, Vec<u8>) {
         let devices_section = {
@@ -170,8 +170,9 @@ fn aerogpu_snapshot_vram_sparse_page_list_tracks_only_non_zero_pages() {
         let page_count = read_u32(data, &mut off);
 
         assert_ne!(vram_len, 0);
+        // The snapshotted length is capped at the BAR1 aperture size (default config here).
         assert!(
-            (vram_len as usize) <= aero_gpu_vga::DEFAULT_VRAM_SIZE,
+            u64::from(vram_len) <= profile::AEROGPU_VRAM_SIZE,
             "unexpected VRAM snapshot length: {vram_len}"
         );
         assert_eq!(page_size, 4096, "unexpected VRAM snapshot page size");
@@ -0,0 +1,194 @@
+use aero_machine::{Machine, MachineConfig};
+use aero_protocol::aerogpu::aerogpu_cmd::{
+    AEROGPU_CLEAR_COLOR, AEROGPU_RESOURCE_USAGE_RENDER_TARGET, AEROGPU_RESOURCE_USAGE_SCANOUT,
+    AEROGPU_RESOURCE_USAGE_TEXTURE,
+};
+use aero_protocol::aerogpu::aerogpu_pci::AerogpuFormat;
+use aero_protocol::aerogpu::cmd_writer::AerogpuCmdWriter;
+use aero_protocol::aerogpu::{aerogpu_pci as pci, aerogpu_ring as ring};
+use pretty_assertions::assert_eq;
+
+const RING_GPA: u64 = 0x1000;
+const FENCE_GPA: u64 = 0x2000;
+const CMD_GPA: u64 = 0x3000;
+const ALLOC_TABLE_GPA: u64 = 0x3800;
+const TEXELS_GPA: u64 = 0x4000;
+
+const RT: u32 = 1;
+const TEX: u32 = 2;
+const TEXELS_ALLOC_ID: u32 = 7;
+
+fn write_alloc_table(m: &mut Machine) -> u32 {
+    let size =
+        (ring::AerogpuAllocTableHeader::SIZE_BYTES + ring::AerogpuAllocEntry::SIZE_BYTES) as u32;
+    m.write_physical_u32(ALLOC_TABLE_GPA, ring::AEROGPU_ALLOC_TABLE_MAGIC);
+    m.write_physical_u32(ALLOC_TABLE_GPA + 4, pci::AEROGPU_ABI_VERSION_U32);
+    m.write_physical_u32(ALLOC_TABLE_GPA + 8, size);
+    m.write_physical_u32(ALLOC_TABLE_GPA + 12, 1); // entry_count
+    m.write_physical_u32(
+        ALLOC_TABLE_GPA + 16,
+        ring::AerogpuAllocEntry::SIZE_BYTES as u32,
+    );
+    m.write_physical_u32(ALLOC_TABLE_GPA + 20, 0);
+
+    let entry = ALLOC_TABLE_GPA + ring::AerogpuAllocTableHeader::SIZE_BYTES as u64;
+    m.write_physical_u32(entry, TEXELS_ALLOC_ID);
+    m.write_physical_u32(entry + 4, 0); // flags
+    m.write_physical_u64(entry + 8, TEXELS_GPA);
+    m.write_physical_u64(entry + 16, 0x1000);
+    size
+}
+
+fn submit(m: &mut Machine, cmd_stream: &[u8], fence: u64) {
+    let pci_cfg = m.pci_config_ports().expect("pc platform enabled");
+    let bdf = aero_devices::pci::profile::AEROGPU.bdf;
+    let bar0_base = {
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        let cfg = pci_cfg
+            .bus_mut()
+            .device_config_mut(bdf)
+            .expect("aerogpu device missing from PCI bus");
+        // Enable MMIO decoding + bus mastering so the device is allowed to DMA.
+        let command = cfg.command();
+        cfg.set_command(command | (1 << 1) | (1 << 2));
+        cfg.bar_range(0).expect("missing aerogpu BAR0").base
+    };
+    assert_ne!(bar0_base, 0, "BAR0 should be assigned by BIOS POST");
+
+    m.write_physical(CMD_GPA, cmd_stream);
+    let alloc_table_size = write_alloc_table(m);
+
+    let entry_count = 1u32;
+    let entry_stride_bytes = ring::AerogpuSubmitDesc::SIZE_BYTES as u32;
+    let ring_size_bytes =
+        ring::AerogpuRingHeader::SIZE_BYTES as u32 + entry_count * entry_stride_bytes;
+    m.write_physical_u32(RING_GPA, ring::AEROGPU_RING_MAGIC);
+    m.write_physical_u32(RING_GPA + 4, pci::AEROGPU_ABI_VERSION_U32);
+    m.write_physical_u32(RING_GPA + 8, ring_size_bytes);
+    m.write_physical_u32(RING_GPA + 12, entry_count);
+    m.write_physical_u32(RING_GPA + 16, entry_stride_bytes);
+    m.write_physical_u32(RING_GPA + 20, 0); // flags
+    m.write_physical_u32(RING_GPA + 24, 0); // head
+    m.write_physical_u32(RING_GPA + 28, 1); // tail
+
+    let desc_gpa = RING_GPA + ring::AerogpuRingHeader::SIZE_BYTES as u64;
+    m.write_physical_u32(desc_gpa, ring::AerogpuSubmitDesc::SIZE_BYTES as u32);
+    m.write_physical_u32(desc_gpa + 4, 0); // flags
+    m.write_physical_u32(desc_gpa + 8, 0); // context_id
+    m.write_physical_u32(desc_gpa + 12, 0); // engine_id
+    m.write_physical_u64(desc_gpa + 16, CMD_GPA);
+    m.write_physical_u32(desc_gpa + 24, cmd_stream.len() as u32);
+    m.write_physical_u64(desc_gpa + 32, ALLOC_TABLE_GPA);
+    m.write_physical_u32(desc_gpa + 40, alloc_table_size);
+    m.write_physical_u64(desc_gpa + 48, fence);
+
+    for (reg, value) in [
+        (pci::AEROGPU_MMIO_REG_RING_GPA_LO, RING_GPA as u32),
+        (pci::AEROGPU_MMIO_REG_RING_GPA_HI, (RING_GPA >> 32) as u32),
+        (pci::AEROGPU_MMIO_REG_RING_SIZE_BYTES, ring_size_bytes),
+        (
+            pci::AEROGPU_MMIO_REG_RING_CONTROL,
+            pci::AEROGPU_RING_CONTROL_ENABLE,
+        ),
+        (pci::AEROGPU_MMIO_REG_FENCE_GPA_LO, FENCE_GPA as u32),
+        (pci::AEROGPU_MMIO_REG_FENCE_GPA_HI, (FENCE_GPA >> 32) as u32),
+        (pci::AEROGPU_MMIO_REG_DOORBELL, 1),
+    ] {
+        m.write_physical_u32(bar0_base + u64::from(reg), value);
+    }
+    m.process_aerogpu();
+
+    let completed_lo =
+        m.read_physical_u32(bar0_base + u64::from(pci::AEROGPU_MMIO_REG_COMPLETED_FENCE_LO));
+    let completed_hi =
+        m.read_physical_u32(bar0_base + u64::from(pci::AEROGPU_MMIO_REG_COMPLETED_FENCE_HI));
+    assert_eq!(
+        u64::from(completed_lo) | (u64::from(completed_hi) << 32),
+        fence
+    );
+}
+
+#[test]
+fn software_backend_renders_golden_frame_via_display_present() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        // Keep the machine minimal/deterministic for this unit test.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.aerogpu_set_backend_software();
+
+    // Guest-owned 2x1 BGRA texture: red, green.
+    m.write_physical(TEXELS_GPA, &[0, 0, 0xFF, 0xFF, 0, 0xFF, 0, 0xFF]);
+
+    let mut w = AerogpuCmdWriter::new();
+    w.create_texture2d(
+        RT,
+        AEROGPU_RESOURCE_USAGE_RENDER_TARGET | AEROGPU_RESOURCE_USAGE_SCANOUT,
+        AerogpuFormat::B8G8R8X8Unorm as u32,
+        4,
+        2,
+        1,
+        1,
+        0,
+        0,
+        0,
+    );
+    w.create_texture2d(
+        TEX,
+        AEROGPU_RESOURCE_USAGE_TEXTURE,
+        AerogpuFormat::B8G8R8A8Unorm as u32,
+        2,
+        1,
+        1,
+        1,
+        0,
+        TEXELS_ALLOC_ID,
+        0,
+    );
+    w.set_render_targets(&[RT], 0);
+    w.clear(AEROGPU_CLEAR_COLOR, [0.0, 0.0, 1.0, 1.0], 1.0, 0);
+    w.copy_texture2d(RT, TEX, 0, 0, 0, 0, 1, 1, 0, 0, 2, 1, 0);
+    w.present(0, 0);
+    submit(&mut m, &w.finish(), 3);
+
+    m.display_present(true);
+    assert_eq!(m.display_resolution(), (4, 2));
+
+    let blue = u32::from_le_bytes([0, 0, 0xFF, 0xFF]);
+    let red = u32::from_le_bytes([0xFF, 0, 0, 0xFF]);
+    let green = u32::from_le_bytes([0, 0xFF, 0, 0xFF]);
+    assert_eq!(
+        m.display_framebuffer(),
+        [blue, blue, blue, blue, blue, red, green, blue]
+    );
+}
+
+#[test]
+fn software_backend_completes_fences_for_rejected_streams() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.aerogpu_set_backend_software();
+
+    // PRESENT without a bound render target is rejected, but the fence must still complete.
+    let mut w = AerogpuCmdWriter::new();
+    w.present(0, 0);
+    submit(&mut m, &w.finish(), 9);
+}
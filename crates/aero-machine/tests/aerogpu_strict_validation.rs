@@ -0,0 +1,375 @@
+use aero_devices::pci::{PciBdf, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig, MachineError};
+use aero_protocol::aerogpu::aerogpu_cmd::{
+    AerogpuCmdCreateBuffer, AerogpuCmdOpcode, AerogpuCmdPresent, AerogpuCmdStreamHeader,
+    AEROGPU_CMD_STREAM_MAGIC,
+};
+use aero_protocol::aerogpu::aerogpu_pci::AerogpuErrorCode;
+use aero_protocol::aerogpu::{aerogpu_pci as pci, aerogpu_ring as ring};
+use pretty_assertions::assert_eq;
+
+const RING_GPA: u64 = 0x10000;
+const FENCE_GPA: u64 = 0x20000;
+const CMD_GPA: u64 = 0x30000;
+const ALLOC_TABLE_GPA: u64 = 0x40000;
+const ENTRY_COUNT: u32 = 8;
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_read(PCI_CFG_DATA_PORT + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_write(PCI_CFG_DATA_PORT + (offset & 3), size, value);
+}
+
+struct Gpu {
+    m: Machine,
+    bar0: u64,
+    tail: u32,
+}
+
+impl Gpu {
+    fn new(strict: bool) -> Self {
+        let mut m = Machine::new(MachineConfig {
+            ram_size_bytes: 16 * 1024 * 1024,
+            enable_pc_platform: true,
+            enable_aerogpu: true,
+            enable_vga: false,
+            enable_serial: false,
+            enable_i8042: false,
+            enable_a20_gate: false,
+            enable_reset_ctrl: false,
+            ..Default::default()
+        })
+        .unwrap();
+        m.aerogpu_set_strict_validation(strict).unwrap();
+        // The validation mode is host configuration and must survive reset.
+        m.reset();
+
+        let bdf = PciBdf::new(0, 0x07, 0);
+        let bar0 = u64::from(cfg_read(&mut m, bdf, 0x10, 4) & !0xFu32);
+        assert_ne!(bar0, 0, "expected AeroGPU BAR0 to be assigned");
+        let mut command = cfg_read(&mut m, bdf, 0x04, 2) as u16;
+        command |= 1 << 2; // COMMAND.BME
+        cfg_write(&mut m, bdf, 0x04, 2, u32::from(command));
+
+        let entry_stride_bytes = ring::AerogpuSubmitDesc::SIZE_BYTES as u32;
+        let ring_size_bytes =
+            ring::AerogpuRingHeader::SIZE_BYTES as u32 + ENTRY_COUNT * entry_stride_bytes;
+        m.write_physical_u32(RING_GPA, ring::AEROGPU_RING_MAGIC);
+        m.write_physical_u32(RING_GPA + 4, pci::AEROGPU_ABI_VERSION_U32);
+        m.write_physical_u32(RING_GPA + 8, ring_size_bytes);
+        m.write_physical_u32(RING_GPA + 12, ENTRY_COUNT);
+        m.write_physical_u32(RING_GPA + 16, entry_stride_bytes);
+        m.write_physical_u32(RING_GPA + 20, 0); // flags
+        m.write_physical_u32(RING_GPA + 24, 0); // head
+        m.write_physical_u32(RING_GPA + 28, 0); // tail
+
+        let mut gpu = Self { m, bar0, tail: 0 };
+        gpu.write_reg(pci::AEROGPU_MMIO_REG_RING_GPA_LO, RING_GPA as u32);
+        gpu.write_reg(pci::AEROGPU_MMIO_REG_RING_GPA_HI, (RING_GPA >> 32) as u32);
+        gpu.write_reg(pci::AEROGPU_MMIO_REG_RING_SIZE_BYTES, ring_size_bytes);
+        gpu.write_reg(
+            pci::AEROGPU_MMIO_REG_RING_CONTROL,
+            pci::AEROGPU_RING_CONTROL_ENABLE,
+        );
+        gpu.write_reg(pci::AEROGPU_MMIO_REG_FENCE_GPA_LO, FENCE_GPA as u32);
+        gpu.write_reg(pci::AEROGPU_MMIO_REG_FENCE_GPA_HI, (FENCE_GPA >> 32) as u32);
+        gpu.write_reg(
+            pci::AEROGPU_MMIO_REG_IRQ_ENABLE,
+            pci::AEROGPU_IRQ_FENCE | pci::AEROGPU_IRQ_ERROR,
+        );
+        gpu
+    }
+
+    fn write_reg(&mut self, reg: u32, value: u32) {
+        self.m.write_physical_u32(self.bar0 + u64::from(reg), value);
+    }
+
+    fn read_reg(&mut self, reg: u32) -> u32 {
+        self.m.read_physical_u32(self.bar0 + u64::from(reg))
+    }
+
+    fn completed_fence(&mut self) -> u64 {
+        let lo = self.read_reg(pci::AEROGPU_MMIO_REG_COMPLETED_FENCE_LO);
+        let hi = self.read_reg(pci::AEROGPU_MMIO_REG_COMPLETED_FENCE_HI);
+        u64::from(lo) | (u64::from(hi) << 32)
+    }
+
+    fn error_code(&mut self) -> u32 {
+        self.read_reg(pci::AEROGPU_MMIO_REG_ERROR_CODE)
+    }
+
+    fn irq_error_pending(&mut self) -> bool {
+        self.read_reg(pci::AEROGPU_MMIO_REG_IRQ_STATUS) & pci::AEROGPU_IRQ_ERROR != 0
+    }
+
+    /// Place `cmd` (and `alloc_table`, if any) in guest memory, append one submit desc and ring the
+    /// doorbell.
+    fn submit(&mut self, cmd: &[u8], alloc_table: Option<&[u8]>, signal_fence: u64) {
+        self.m.write_physical(CMD_GPA, cmd);
+        let (alloc_gpa, alloc_size) = match alloc_table {
+            Some(table) => {
+                self.m.write_physical(ALLOC_TABLE_GPA, table);
+                (ALLOC_TABLE_GPA, table.len() as u32)
+            }
+            None => (0, 0),
+        };
+
+        let desc_gpa = RING_GPA
+            + ring::AerogpuRingHeader::SIZE_BYTES as u64
+            + u64::from(self.tail % ENTRY_COUNT) * ring::AerogpuSubmitDesc::SIZE_BYTES as u64;
+        let m = &mut self.m;
+        m.write_physical_u32(desc_gpa, ring::AerogpuSubmitDesc::SIZE_BYTES as u32);
+        m.write_physical_u32(desc_gpa + 4, 0); // flags
+        m.write_physical_u32(desc_gpa + 8, 0); // context_id
+        m.write_physical_u32(desc_gpa + 12, ring::AEROGPU_ENGINE_0);
+        m.write_physical_u64(desc_gpa + 16, CMD_GPA);
+        m.write_physical_u32(desc_gpa + 24, cmd.len() as u32);
+        m.write_physical_u32(desc_gpa + 28, 0);
+        m.write_physical_u64(desc_gpa + 32, alloc_gpa);
+        m.write_physical_u32(desc_gpa + 40, alloc_size);
+        m.write_physical_u32(desc_gpa + 44, 0);
+        m.write_physical_u64(desc_gpa + 48, signal_fence);
+        m.write_physical_u64(desc_gpa + 56, 0);
+
+        self.tail = self.tail.wrapping_add(1);
+        self.m.write_physical_u32(RING_GPA + 28, self.tail);
+        self.write_reg(pci::AEROGPU_MMIO_REG_DOORBELL, 1);
+        self.m.process_aerogpu();
+        self.m.poll_pci_intx_lines();
+        assert_eq!(
+            self.m.read_physical_u32(RING_GPA + 24),
+            self.tail,
+            "submissions are always consumed"
+        );
+    }
+
+    /// Submit a malformed stream and check that it was rejected with `code`.
+    fn expect_rejected(
+        &mut self,
+        cmd: &[u8],
+        alloc_table: Option<&[u8]>,
+        signal_fence: u64,
+        code: AerogpuErrorCode,
+    ) {
+        let completed_before = self.completed_fence();
+        self.submit(cmd, alloc_table, signal_fence);
+
+        assert_eq!(self.error_code(), code as u32);
+        let error_fence = u64::from(self.read_reg(pci::AEROGPU_MMIO_REG_ERROR_FENCE_LO))
+            | (u64::from(self.read_reg(pci::AEROGPU_MMIO_REG_ERROR_FENCE_HI)) << 32);
+        assert_eq!(error_fence, signal_fence);
+        assert!(self.irq_error_pending());
+        assert_eq!(
+            self.completed_fence(),
+            completed_before,
+            "a rejected submission must not complete its fence"
+        );
+
+        let failures = self.m.aerogpu_take_validation_failures();
+        assert_eq!(failures.len(), 1, "{failures:?}");
+        let failure = &failures[0];
+        assert_eq!(failure.code, code);
+        assert_eq!(failure.ring_index, self.tail - 1);
+        assert_eq!(failure.signal_fence, signal_fence);
+        assert_eq!(failure.cmd_gpa, CMD_GPA);
+        assert_eq!(failure.cmd_stream, cmd);
+        assert_eq!(failure.alloc_table, alloc_table.unwrap_or_default());
+        assert!(!failure.detail.is_empty());
+
+        self.write_reg(pci::AEROGPU_MMIO_REG_IRQ_ACK, pci::AEROGPU_IRQ_ERROR);
+    }
+}
+
+fn packet(opcode: u32, size_bytes: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&opcode.to_le_bytes());
+    out.extend_from_slice(&size_bytes.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+fn present() -> Vec<u8> {
+    // scanout_id = 0, flags = 0 (no vsync).
+    packet(
+        AerogpuCmdOpcode::Present as u32,
+        AerogpuCmdPresent::SIZE_BYTES as u32,
+        &[0u8; 8],
+    )
+}
+
+fn create_buffer(backing_alloc_id: u32) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&1u32.to_le_bytes()); // buffer_handle
+    payload.extend_from_slice(&0u32.to_le_bytes()); // usage_flags
+    payload.extend_from_slice(&0x1000u64.to_le_bytes()); // size_bytes
+    payload.extend_from_slice(&backing_alloc_id.to_le_bytes());
+    payload.extend_from_slice(&0u32.to_le_bytes()); // backing_offset_bytes
+    payload.extend_from_slice(&0u64.to_le_bytes()); // reserved0
+    packet(
+        AerogpuCmdOpcode::CreateBuffer as u32,
+        AerogpuCmdCreateBuffer::SIZE_BYTES as u32,
+        &payload,
+    )
+}
+
+fn stream_with_header(magic: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = packets.concat();
+    let size_bytes = (AerogpuCmdStreamHeader::SIZE_BYTES + body.len()) as u32;
+    let mut out = Vec::new();
+    out.extend_from_slice(&magic.to_le_bytes());
+    out.extend_from_slice(&pci::AEROGPU_ABI_VERSION_U32.to_le_bytes());
+    out.extend_from_slice(&size_bytes.to_le_bytes());
+    out.extend_from_slice(&[0u8; 12]); // flags, reserved0, reserved1
+    out.extend_from_slice(&body);
+    out
+}
+
+fn stream(packets: &[Vec<u8>]) -> Vec<u8> {
+    stream_with_header(AEROGPU_CMD_STREAM_MAGIC, packets)
+}
+
+fn alloc_table(alloc_ids: &[u32]) -> Vec<u8> {
+    let size_bytes = (ring::AerogpuAllocTableHeader::SIZE_BYTES
+        + alloc_ids.len() * ring::AerogpuAllocEntry::SIZE_BYTES) as u32;
+    let mut out = Vec::new();
+    out.extend_from_slice(&ring::AEROGPU_ALLOC_TABLE_MAGIC.to_le_bytes());
+    out.extend_from_slice(&pci::AEROGPU_ABI_VERSION_U32.to_le_bytes());
+    out.extend_from_slice(&size_bytes.to_le_bytes());
+    out.extend_from_slice(&(alloc_ids.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(ring::AerogpuAllocEntry::SIZE_BYTES as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for (i, alloc_id) in alloc_ids.iter().enumerate() {
+        out.extend_from_slice(&alloc_id.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes()); // flags
+        out.extend_from_slice(&(0x100000u64 + i as u64 * 0x1000).to_le_bytes()); // gpa
+        out.extend_from_slice(&0x1000u64.to_le_bytes()); // size_bytes
+        out.extend_from_slice(&0u64.to_le_bytes()); // reserved0
+    }
+    out
+}
+
+#[test]
+fn strict_mode_executes_well_formed_submissions() {
+    let mut gpu = Gpu::new(true);
+    gpu.submit(&stream(&[present()]), None, 1);
+    gpu.submit(
+        &stream(&[create_buffer(7), present()]),
+        Some(&alloc_table(&[7])),
+        2,
+    );
+    // Repeating a fence value is legitimate.
+    gpu.submit(&stream(&[]), None, 2);
+
+    assert_eq!(gpu.error_code(), AerogpuErrorCode::None as u32);
+    assert!(!gpu.irq_error_pending());
+    assert_eq!(gpu.completed_fence(), 2);
+    assert!(gpu.m.aerogpu_take_validation_failures().is_empty());
+}
+
+#[test]
+fn strict_mode_rejects_bad_stream_header() {
+    let mut gpu = Gpu::new(true);
+    gpu.expect_rejected(
+        &stream_with_header(0xDEAD_BEEF, &[present()]),
+        None,
+        1,
+        AerogpuErrorCode::CmdStreamHeader,
+    );
+
+    // Header size_bytes larger than the submitted buffer.
+    let mut cmd = stream(&[present()]);
+    cmd[8..12].copy_from_slice(&0x1000u32.to_le_bytes());
+    gpu.expect_rejected(&cmd, None, 2, AerogpuErrorCode::CmdStreamHeader);
+}
+
+#[test]
+fn strict_mode_rejects_unaligned_packet() {
+    let mut gpu = Gpu::new(true);
+    let cmd = stream(&[packet(AerogpuCmdOpcode::Nop as u32, 10, &[0u8; 4])]);
+    gpu.expect_rejected(&cmd, None, 1, AerogpuErrorCode::PacketAlignment);
+}
+
+#[test]
+fn strict_mode_rejects_bad_packet_sizes() {
+    let mut gpu = Gpu::new(true);
+
+    // Shorter than the fixed PRESENT layout.
+    let cmd = stream(&[packet(AerogpuCmdOpcode::Present as u32, 12, &[0u8; 4])]);
+    gpu.expect_rejected(&cmd, None, 1, AerogpuErrorCode::PacketSize);
+
+    // Overruns the stream.
+    let cmd = stream(&[packet(AerogpuCmdOpcode::Nop as u32, 64, &[0u8; 8])]);
+    gpu.expect_rejected(&cmd, None, 2, AerogpuErrorCode::PacketSize);
+
+    // Zero-sized packets would never advance.
+    let cmd = stream(&[packet(AerogpuCmdOpcode::Nop as u32, 0, &[])]);
+    gpu.expect_rejected(&cmd, None, 3, AerogpuErrorCode::PacketSize);
+}
+
+#[test]
+fn strict_mode_rejects_missing_alloc_table_entries() {
+    let mut gpu = Gpu::new(true);
+    let cmd = stream(&[create_buffer(9)]);
+
+    gpu.expect_rejected(&cmd, None, 1, AerogpuErrorCode::AllocTable);
+    gpu.expect_rejected(
+        &cmd,
+        Some(&alloc_table(&[1, 2])),
+        2,
+        AerogpuErrorCode::AllocTable,
+    );
+
+    let mut table = alloc_table(&[9]);
+    table[0] ^= 0xFF; // magic
+    gpu.expect_rejected(&cmd, Some(&table), 3, AerogpuErrorCode::AllocTable);
+}
+
+#[test]
+fn strict_mode_rejects_fence_going_backwards() {
+    let mut gpu = Gpu::new(true);
+    gpu.submit(&stream(&[present()]), None, 10);
+    assert_eq!(gpu.completed_fence(), 10);
+
+    gpu.expect_rejected(&stream(&[present()]), None, 5, AerogpuErrorCode::FenceOrder);
+
+    // The rejected fence is not remembered; ordering continues from the last accepted one.
+    gpu.submit(&stream(&[present()]), None, 11);
+    assert_eq!(gpu.completed_fence(), 11);
+}
+
+#[test]
+fn permissive_mode_is_the_default() {
+    let mut gpu = Gpu::new(false);
+    let cmd = stream(&[packet(AerogpuCmdOpcode::Nop as u32, 10, &[0u8; 4])]);
+    gpu.submit(&cmd, None, 1);
+
+    assert_eq!(gpu.error_code(), AerogpuErrorCode::None as u32);
+    assert_eq!(gpu.completed_fence(), 1);
+    assert!(gpu.m.aerogpu_take_validation_failures().is_empty());
+}
+
+#[test]
+fn strict_validation_requires_aerogpu() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(matches!(
+        m.aerogpu_set_strict_validation(true),
+        Err(MachineError::AeroGpuNotEnabled)
+    ));
+    assert!(m.aerogpu_take_validation_failures().is_empty());
+}
@@ -108,7 +108,7 @@ fn aerogpu_text_mode_page_selection_and_cursor_render_from_bda() {
     m.reset();
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     let (w, _h) = m.display_resolution();
     let fb = m.display_framebuffer();
 
@@ -145,7 +145,7 @@ fn aerogpu_text_mode_cursor_disable_bit_respected() {
     m.reset();
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     let (w, _h) = m.display_resolution();
     let fb = m.display_framebuffer();
 
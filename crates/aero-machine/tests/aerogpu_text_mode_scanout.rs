@@ -77,7 +77,7 @@ fn aerogpu_text_mode_scanout_renders_b8000_cell() {
     m.write_physical_u8(0xB8000, b'A');
     m.write_physical_u8(0xB8001, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
 
     let (w, h) = m.display_resolution();
     assert_ne!((w, h), (0, 0), "expected non-zero scanout resolution");
@@ -151,7 +151,7 @@ fn aerogpu_text_mode_scanout_honors_attribute_palette_mapping() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x10); // bg=1, fg=0
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (720, 400));
     // RGBA8888 little-endian u32: [R, G, B, A].
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
@@ -191,7 +191,7 @@ fn aerogpu_text_mode_scanout_honors_blink_bit_for_background_color() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x80);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (720, 400));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_0000);
 }
@@ -259,7 +259,7 @@ fn aerogpu_text_mode_scanout_renders_cursor_overlay() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
     let (w, h) = m.display_resolution();
     assert_ne!((w, h), (0, 0), "expected non-zero scanout resolution");
 
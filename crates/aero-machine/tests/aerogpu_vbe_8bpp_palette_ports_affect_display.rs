@@ -69,7 +69,7 @@ fn aerogpu_vbe_8bpp_render_uses_vga_dac_palette() {
     let lfb_base = m.vbe_lfb_base();
     m.write_physical_u8(lfb_base, 1);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
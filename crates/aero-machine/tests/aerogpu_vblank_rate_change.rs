@@ -0,0 +1,234 @@
+use aero_devices::pci::profile::AEROGPU_BAR0_INDEX;
+use aero_machine::{
+    Machine, MachineConfig, MachineError, AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ,
+    AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ,
+};
+use aero_protocol::aerogpu::aerogpu_pci as pci;
+use pretty_assertions::assert_eq;
+
+const PERIOD_60HZ_NS: u64 = 16_666_667;
+const PERIOD_30HZ_NS: u64 = 33_333_334;
+const PERIOD_120HZ_NS: u64 = 8_333_334;
+
+/// Host tick granularity; smaller than every period under test so each vblank is observed.
+const STEP_NS: u64 = 1_000_000;
+
+fn machine_cfg() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        // Keep the machine minimal/deterministic for this unit test.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn bar0(m: &mut Machine) -> u64 {
+    let bdf = m.aerogpu_bdf().expect("AeroGPU device should be present");
+    let pci_cfg = m.pci_config_ports().expect("pc platform enabled");
+    {
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        let cfg = pci_cfg
+            .bus_mut()
+            .device_config_mut(bdf)
+            .expect("AeroGPU PCI function missing");
+        cfg.set_command(cfg.command() | (1 << 1) | (1 << 2));
+    }
+    m.pci_bar_base(bdf, AEROGPU_BAR0_INDEX)
+        .expect("AeroGPU BAR0 should be mapped")
+}
+
+fn read_u64(m: &mut Machine, base: u64, lo: u32, hi: u32) -> u64 {
+    let lo = m.read_physical_u32(base + u64::from(lo));
+    let hi = m.read_physical_u32(base + u64::from(hi));
+    u64::from(lo) | (u64::from(hi) << 32)
+}
+
+fn vblank_seq(m: &mut Machine, bar0: u64) -> u64 {
+    read_u64(
+        m,
+        bar0,
+        pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_SEQ_LO,
+        pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_SEQ_HI,
+    )
+}
+
+fn period_reg(m: &mut Machine, bar0: u64) -> u64 {
+    u64::from(
+        m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS)),
+    )
+}
+
+/// Advance `duration_ns` in small steps and return the timestamp of every vblank observed,
+/// asserting that `VBLANK_SEQ` advances by exactly one per vblank IRQ.
+fn collect_vblanks(m: &mut Machine, bar0: u64, duration_ns: u64) -> Vec<u64> {
+    let mut times = Vec::new();
+    let mut seq = vblank_seq(m, bar0);
+    for _ in 0..duration_ns / STEP_NS {
+        m.tick_platform(STEP_NS);
+        let next_seq = vblank_seq(m, bar0);
+        let status = m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_STATUS));
+        if next_seq == seq {
+            assert_eq!(status & pci::AEROGPU_IRQ_SCANOUT_VBLANK, 0);
+            continue;
+        }
+        assert_eq!(next_seq, seq + 1, "vblank missed or doubled");
+        assert_ne!(status & pci::AEROGPU_IRQ_SCANOUT_VBLANK, 0);
+        m.write_physical_u32(
+            bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ACK),
+            pci::AEROGPU_IRQ_SCANOUT_VBLANK,
+        );
+        seq = next_seq;
+        times.push(read_u64(
+            m,
+            bar0,
+            pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_LO,
+            pci::AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_HI,
+        ));
+    }
+    times
+}
+
+fn spacings(last_before: u64, times: &[u64]) -> Vec<u64> {
+    std::iter::once(last_before)
+        .chain(times.iter().copied())
+        .collect::<Vec<_>>()
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .collect()
+}
+
+/// Change the rate and check the guest-visible side of the transition.
+fn change_rate(m: &mut Machine, bar0: u64, millihertz: u32, expected_period_ns: u64) {
+    m.aerogpu_set_vblank_rate(millihertz).unwrap();
+    assert_eq!(period_reg(m, bar0), expected_period_ns);
+    let status = m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_STATUS));
+    assert_ne!(status & pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED, 0);
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ACK),
+        pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED,
+    );
+}
+
+#[test]
+fn vblank_rate_changes_mid_run_keep_spacing_and_sequence() {
+    let mut m = Machine::new(machine_cfg()).unwrap();
+    let bar0 = bar0(&mut m);
+
+    let features = read_u64(
+        &mut m,
+        bar0,
+        pci::AEROGPU_MMIO_REG_FEATURES_LO,
+        pci::AEROGPU_MMIO_REG_FEATURES_HI,
+    );
+    assert_ne!(features & pci::AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE, 0);
+
+    m.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_SCANOUT_VBLANK | pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED,
+    );
+    m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE), 1);
+    assert_eq!(period_reg(&mut m, bar0), PERIOD_60HZ_NS);
+
+    // 60Hz.
+    let at_60 = collect_vblanks(&mut m, bar0, 100_000_000);
+    assert!(at_60.len() >= 5);
+    assert!(spacings(at_60[0], &at_60[1..])
+        .iter()
+        .all(|&d| d == PERIOD_60HZ_NS));
+
+    // 60 -> 30Hz: the already-scheduled 60Hz edge fires once more, then 30Hz spacing.
+    change_rate(&mut m, bar0, 30_000, PERIOD_30HZ_NS);
+    let at_30 = collect_vblanks(&mut m, bar0, 200_000_000);
+    assert!(at_30.len() >= 5);
+    let d = spacings(*at_60.last().unwrap(), &at_30);
+    assert_eq!(d[0], PERIOD_60HZ_NS);
+    assert!(d[1..].iter().all(|&d| d == PERIOD_30HZ_NS), "{d:?}");
+
+    // 30 -> 120Hz.
+    change_rate(&mut m, bar0, 120_000, PERIOD_120HZ_NS);
+    let at_120 = collect_vblanks(&mut m, bar0, 100_000_000);
+    assert!(at_120.len() >= 10);
+    let d = spacings(*at_30.last().unwrap(), &at_120);
+    assert_eq!(d[0], PERIOD_30HZ_NS);
+    assert!(d[1..].iter().all(|&d| d == PERIOD_120HZ_NS), "{d:?}");
+
+    // Setting the same rate again is not a change.
+    m.aerogpu_set_vblank_rate(120_000).unwrap();
+    let status = m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_STATUS));
+    assert_eq!(status & pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED, 0);
+}
+
+#[test]
+fn vblank_rate_is_clamped_and_period_changed_irq_respects_enable() {
+    let mut m = Machine::new(machine_cfg()).unwrap();
+    let bar0 = bar0(&mut m);
+
+    // Not enabled in IRQ_ENABLE: the register updates but no IRQ cause is latched.
+    m.aerogpu_set_vblank_rate(1).unwrap();
+    assert_eq!(
+        period_reg(&mut m, bar0),
+        1_000_000_000_000 / u64::from(AEROGPU_VBLANK_RATE_MIN_MILLIHERTZ)
+    );
+    let status = m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_STATUS));
+    assert_eq!(status & pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED, 0);
+
+    m.aerogpu_set_vblank_rate(u32::MAX).unwrap();
+    assert_eq!(
+        period_reg(&mut m, bar0),
+        1_000_000_000_000u64.div_ceil(u64::from(AEROGPU_VBLANK_RATE_MAX_MILLIHERTZ))
+    );
+
+    let mut no_gpu = Machine::new(MachineConfig {
+        enable_aerogpu: false,
+        ..machine_cfg()
+    })
+    .unwrap();
+    assert!(matches!(
+        no_gpu.aerogpu_set_vblank_rate(30_000),
+        Err(MachineError::AeroGpuNotEnabled)
+    ));
+}
+
+#[test]
+fn vblank_rate_survives_reset_and_snapshot() {
+    let cfg = machine_cfg();
+    let mut m = Machine::new(cfg.clone()).unwrap();
+    m.aerogpu_set_vblank_rate(120_000).unwrap();
+
+    m.reset();
+    let bar0_after_reset = bar0(&mut m);
+    assert_eq!(period_reg(&mut m, bar0_after_reset), PERIOD_120HZ_NS);
+
+    m.write_physical_u32(
+        bar0_after_reset + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE),
+        1,
+    );
+    m.tick_platform(3 * PERIOD_120HZ_NS);
+    let seq_before = vblank_seq(&mut m, bar0_after_reset);
+    assert!(seq_before > 0);
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = Machine::new(cfg).unwrap();
+    restored.reset();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    let bar0 = bar0(&mut restored);
+    assert_eq!(period_reg(&mut restored, bar0), PERIOD_120HZ_NS);
+    assert_eq!(vblank_seq(&mut restored, bar0), seq_before);
+
+    // Pacing resumes at the restored rate.
+    restored.write_physical_u32(
+        bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE),
+        pci::AEROGPU_IRQ_SCANOUT_VBLANK,
+    );
+    let times = collect_vblanks(&mut restored, bar0, 50_000_000);
+    assert!(times.len() >= 4);
+    assert!(spacings(times[0], &times[1..])
+        .iter()
+        .all(|&d| d == PERIOD_120HZ_NS));
+}
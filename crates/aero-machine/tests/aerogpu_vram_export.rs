@@ -0,0 +1,86 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_machine::{Machine, MachineConfig};
+use aero_platform::reset::ResetKind;
+use pretty_assertions::assert_eq;
+
+const OFFSET: usize = 0x20_0000;
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 64 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_aerogpu: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn export_aliases_bar1_and_is_invalidated_by_cold_reset() {
+    let mut m = new_machine();
+    let export = m.aerogpu_vram_export().expect("AeroGPU is enabled");
+    let bar1 = export
+        .bar1_base
+        .expect("AeroGPU BAR1 base should be assigned");
+    assert_eq!(Some(bar1), m.aerogpu_vram_bar_base());
+    assert!(export.len > OFFSET + 4);
+    assert_eq!(export.generation, m.aerogpu_vram_generation());
+
+    // Host writes through the export are visible to guest BAR1 reads...
+    let reads_before = m.aerogpu_bar1_mmio_read_count().unwrap();
+    // SAFETY: the machine is alive, not running, and `OFFSET + 4 <= len`.
+    unsafe {
+        let bytes = 0x1234_5678u32.to_le_bytes();
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), export.ptr.add(OFFSET), bytes.len());
+    }
+    assert_eq!(m.read_physical_u32(bar1 + OFFSET as u64), 0x1234_5678);
+    assert!(m.aerogpu_bar1_mmio_read_count().unwrap() > reads_before);
+
+    // ...and guest BAR1 writes are visible through the export.
+    m.write_physical_u32(bar1 + OFFSET as u64 + 4, 0xCAFE_F00D);
+    let mut bytes = [0u8; 4];
+    // SAFETY: as above.
+    unsafe {
+        std::ptr::copy_nonoverlapping(export.ptr.add(OFFSET + 4), bytes.as_mut_ptr(), 4);
+    }
+    assert_eq!(u32::from_le_bytes(bytes), 0xCAFE_F00D);
+
+    // A warm reset keeps VRAM, so the export stays current.
+    m.reset_with_kind(ResetKind::Cpu);
+    assert_eq!(m.aerogpu_vram_generation(), export.generation);
+    assert_eq!(m.aerogpu_vram_export(), Some(export));
+
+    // A cold reset clears VRAM and invalidates outstanding exports.
+    m.reset_with_kind(ResetKind::System);
+    assert_ne!(m.aerogpu_vram_generation(), export.generation);
+    let fresh = m.aerogpu_vram_export().unwrap();
+    assert_eq!(fresh.generation, m.aerogpu_vram_generation());
+    assert_eq!(m.read_physical_u32(bar1 + OFFSET as u64), 0);
+}
+
+#[test]
+fn snapshot_restore_bumps_the_generation() {
+    let mut m = new_machine();
+    let snap = m.take_snapshot_full().unwrap();
+    let before = m.aerogpu_vram_generation();
+    m.restore_snapshot_bytes(&snap).unwrap();
+    assert_ne!(m.aerogpu_vram_generation(), before);
+}
+
+#[test]
+fn no_export_without_aerogpu() {
+    let m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(m.aerogpu_vram_export(), None);
+}
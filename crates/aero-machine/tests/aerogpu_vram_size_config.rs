@@ -0,0 +1,108 @@
+use aero_devices::pci::profile;
+use aero_machine::{Machine, MachineConfig, AEROGPU_VRAM_SIZE_MIN, VBE_LFB_OFFSET};
+use aero_pc_constants::{PCI_MMIO_BASE, PCI_MMIO_SIZE};
+use pretty_assertions::assert_eq;
+
+fn aerogpu_cfg(vram_size: usize) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        aerogpu_vram_size_bytes: Some(vram_size),
+        // Keep the machine minimal.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    }
+}
+
+/// Runs the standard BAR sizing probe (write all-ones, read back the size mask) on BAR1.
+fn probe_bar1_size(m: &mut Machine) -> u64 {
+    let bdf = profile::AEROGPU.bdf;
+    let pci_cfg = m.pci_config_ports().expect("pc platform enabled");
+    let mut pci_cfg = pci_cfg.borrow_mut();
+    let bus = pci_cfg.bus_mut();
+
+    let bar1_off = 0x10 + 4 * u16::from(profile::AEROGPU_BAR1_VRAM_INDEX);
+    let orig = bus.read_config(bdf, bar1_off, 4);
+    bus.write_config(bdf, bar1_off, 4, 0xFFFF_FFFF);
+    let mask = bus.read_config(bdf, bar1_off, 4) & !0xF;
+    bus.write_config(bdf, bar1_off, 4, orig);
+    assert_eq!(bus.read_config(bdf, bar1_off, 4), orig);
+
+    u64::from(!mask) + 1
+}
+
+fn vbe_dispi_video_memory_64k(m: &mut Machine) -> u16 {
+    // VBE_DISPI_INDEX_VIDEO_MEMORY_64K.
+    m.io_write(0x01CE, 2, 0x000A);
+    m.io_read(0x01CF, 2) as u16
+}
+
+fn assert_vram_size_is_guest_visible(vram_size: usize) {
+    let mut m = Machine::new(aerogpu_cfg(vram_size)).unwrap();
+    let vram_size = vram_size as u64;
+
+    assert_eq!(probe_bar1_size(&mut m), vram_size);
+
+    let bar1 = m
+        .pci_bar_base(profile::AEROGPU.bdf, profile::AEROGPU_BAR1_VRAM_INDEX)
+        .expect("AeroGPU BAR1 should be assigned by PCI BIOS POST");
+    assert_ne!(bar1, 0);
+    assert_eq!(bar1 % vram_size, 0, "BAR1 must be naturally aligned");
+    assert!(bar1 >= PCI_MMIO_BASE && bar1 + vram_size <= PCI_MMIO_BASE + PCI_MMIO_SIZE);
+
+    // Bochs VBE reports the VRAM past the LFB offset in 64KiB units.
+    assert_eq!(
+        u64::from(vbe_dispi_video_memory_64k(&mut m)),
+        (vram_size - VBE_LFB_OFFSET as u64) / (64 * 1024)
+    );
+
+    // The last dword of the aperture is backed by VRAM, both through BAR1 and via snapshots.
+    let top = bar1 + vram_size - 4;
+    m.write_physical_u32(top, 0xC0FF_EE11);
+    assert_eq!(m.read_physical_u32(top), 0xC0FF_EE11);
+    // ...and the first dword of the VBE LFB is still distinct storage.
+    assert_eq!(m.read_physical_u32(bar1 + VBE_LFB_OFFSET as u64), 0);
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = Machine::new(aerogpu_cfg(vram_size as usize)).unwrap();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    let restored_bar1 = restored
+        .pci_bar_base(profile::AEROGPU.bdf, profile::AEROGPU_BAR1_VRAM_INDEX)
+        .unwrap();
+    assert_eq!(restored_bar1, bar1);
+    assert_eq!(restored.read_physical_u32(top), 0xC0FF_EE11);
+
+    // BIOS POST on reset keeps the same aperture.
+    m.reset();
+    assert_eq!(probe_bar1_size(&mut m), vram_size);
+    assert_eq!(
+        m.pci_bar_base(profile::AEROGPU.bdf, profile::AEROGPU_BAR1_VRAM_INDEX),
+        Some(bar1)
+    );
+}
+
+#[test]
+fn aerogpu_vram_size_min_sizes_bar1_and_backs_top_of_vram() {
+    assert_vram_size_is_guest_visible(AEROGPU_VRAM_SIZE_MIN);
+}
+
+#[test]
+fn aerogpu_vram_size_large_sizes_bar1_and_backs_top_of_vram() {
+    assert_vram_size_is_guest_visible(256 * 1024 * 1024);
+}
+
+#[test]
+fn aerogpu_vram_size_defaults_to_profile_bar_size() {
+    let mut m = Machine::new(MachineConfig {
+        aerogpu_vram_size_bytes: None,
+        ..aerogpu_cfg(AEROGPU_VRAM_SIZE_MIN)
+    })
+    .unwrap();
+    assert_eq!(probe_bar1_size(&mut m), profile::AEROGPU_VRAM_SIZE);
+}
@@ -295,7 +295,7 @@ fn aerogpu_wgpu_backend_smoke_executes_acmd_and_updates_scanout() {
     assert_eq!(irq_status & pci::AEROGPU_IRQ_FENCE, 0);
 
     // Host-visible scanout output should contain the clear color.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (width, height));
 
     let fb = m.display_framebuffer();
@@ -0,0 +1,210 @@
+use aero_machine::{Machine, MachineConfig, RunExit};
+use firmware::bios::{e820_from_memory_map, E820Entry, PCIE_ECAM_BASE, PCIE_ECAM_SIZE};
+use pretty_assertions::assert_eq;
+
+const ONE_MIB: u64 = 0x10_0000;
+const SIXTEEN_MIB: u64 = 0x100_0000;
+const FOUR_GIB: u64 = 0x1_0000_0000;
+
+const E820_RAM: u32 = 1;
+const E820_RESERVED: u32 = 2;
+const E820_ACPI: u32 = 3;
+const E820_NVS: u32 = 4;
+
+const RESULTS: u64 = 0x1000;
+const ENTRIES: u64 = 0x1100;
+
+/// Boot sector that calls INT 15h AH=88h, AX=E801h and walks AX=E820h, storing the results at
+/// [`RESULTS`] (entry count, AH=88h AX, E801h AX/BX/CX/DX) and the E820 entries at [`ENTRIES`].
+fn memory_sizing_boot_sector() -> Vec<u8> {
+    let code: &[u8] = &[
+        0xFA, // cli
+        0x31, 0xC0, // xor ax, ax
+        0x8E, 0xD8, // mov ds, ax
+        0x8E, 0xC0, // mov es, ax
+        0x8E, 0xD0, // mov ss, ax
+        0xBC, 0x00, 0x7C, // mov sp, 0x7C00
+        0xC7, 0x06, 0x00, 0x10, 0x00, 0x00, // mov word [0x1000], 0
+        0xB4, 0x88, // mov ah, 0x88
+        0xCD, 0x15, // int 0x15
+        0xA3, 0x02, 0x10, // mov [0x1002], ax
+        0xB8, 0x01, 0xE8, // mov ax, 0xE801
+        0xCD, 0x15, // int 0x15
+        0xA3, 0x04, 0x10, // mov [0x1004], ax
+        0x89, 0x1E, 0x06, 0x10, // mov [0x1006], bx
+        0x89, 0x0E, 0x08, 0x10, // mov [0x1008], cx
+        0x89, 0x16, 0x0A, 0x10, // mov [0x100A], dx
+        0x66, 0x31, 0xDB, // xor ebx, ebx
+        0xBF, 0x00, 0x11, // mov di, 0x1100
+        // next:
+        0x66, 0xB8, 0x20, 0xE8, 0x00, 0x00, // mov eax, 0xE820
+        0x66, 0xBA, 0x50, 0x41, 0x4D, 0x53, // mov edx, 'SMAP'
+        0x66, 0xB9, 0x18, 0x00, 0x00, 0x00, // mov ecx, 24
+        0xCD, 0x15, // int 0x15
+        0x72, 0x0C, // jc done
+        0x83, 0xC7, 0x18, // add di, 24
+        0xFF, 0x06, 0x00, 0x10, // inc word [0x1000]
+        0x66, 0x85, 0xDB, // test ebx, ebx
+        0x75, 0xDE, // jnz next
+        // done:
+        0xF4, // hlt
+    ];
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+struct Sizing {
+    ah88_kb: u16,
+    e801: [u16; 4],
+    e820: Vec<E820Entry>,
+}
+
+fn run_sizing_stub(m: &mut Machine) -> Sizing {
+    m.set_disk_image(memory_sizing_boot_sector()).unwrap();
+    m.reset();
+    for _ in 0..100 {
+        match m.run_slice(50_000) {
+            RunExit::Halted { .. } => break,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+
+    let count = m.read_physical_u16(RESULTS);
+    let e801 = [0, 1, 2, 3].map(|i| m.read_physical_u16(RESULTS + 4 + 2 * i));
+    let e820 = (0..u64::from(count))
+        .map(|i| {
+            let entry = ENTRIES + 24 * i;
+            E820Entry {
+                base: m.read_physical_u64(entry),
+                length: m.read_physical_u64(entry + 8),
+                region_type: m.read_physical_u32(entry + 16),
+                extended_attributes: m.read_physical_u32(entry + 20),
+            }
+        })
+        .collect();
+    Sizing {
+        ah88_kb: m.read_physical_u16(RESULTS + 2),
+        e801,
+        e820,
+    }
+}
+
+fn is_memory(entry: &E820Entry) -> bool {
+    matches!(entry.region_type, E820_RAM | E820_ACPI | E820_NVS)
+}
+
+fn memory_bytes_in(map: &[E820Entry], start: u64, end: u64) -> u64 {
+    map.iter()
+        .filter(|e| is_memory(e))
+        .map(|e| {
+            let lo = e.base.max(start);
+            let hi = (e.base + e.length).min(end);
+            hi.saturating_sub(lo)
+        })
+        .sum()
+}
+
+fn contiguous_memory_end(map: &[E820Entry], from: u64) -> u64 {
+    let mut end = from;
+    while let Some(e) = map
+        .iter()
+        .find(|e| is_memory(e) && e.base <= end && e.base + e.length > end)
+    {
+        end = e.base + e.length;
+    }
+    end
+}
+
+/// AH=88h and E801h must be exactly what the E820 map implies.
+fn assert_coherent(sizing: &Sizing) {
+    let map = &sizing.e820;
+    let ext_kb = ((contiguous_memory_end(map, ONE_MIB) - ONE_MIB) / 1024).min(0xFC00);
+    assert_eq!(u64::from(sizing.ah88_kb), ext_kb, "{map:#x?}");
+
+    let low_kb = (memory_bytes_in(map, ONE_MIB, SIXTEEN_MIB) / 1024).min(0x3C00);
+    let high_blocks = memory_bytes_in(map, SIXTEEN_MIB, FOUR_GIB) / 0x1_0000;
+    assert_eq!(
+        sizing.e801.map(u64::from),
+        [low_kb, high_blocks, low_kb, high_blocks]
+    );
+}
+
+fn assert_reserved(map: &[E820Entry], start: u64, len: u64) {
+    assert!(
+        map.iter().any(|e| e.region_type == E820_RESERVED
+            && e.base <= start
+            && start + len <= e.base + e.length),
+        "{start:#x}+{len:#x} is not reserved: {map:#x?}"
+    );
+    for e in map.iter().filter(|e| is_memory(e)) {
+        assert!(
+            e.base + e.length <= start || start + len <= e.base,
+            "{e:x?} overlaps {start:#x}+{len:#x}"
+        );
+    }
+}
+
+#[test]
+fn small_ram_reserves_ecam_chipset_tpm_and_lfb_windows() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 32 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_aerogpu: true,
+        enable_tpm: true,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let sizing = run_sizing_stub(&mut m);
+
+    // The guest sees exactly the host-visible memory map.
+    assert_eq!(sizing.e820, e820_from_memory_map(&m.guest_memory_map()));
+    assert_coherent(&sizing);
+    assert_eq!(sizing.ah88_kb, 31 * 1024);
+
+    let map = &sizing.e820;
+    assert_reserved(map, PCIE_ECAM_BASE, PCIE_ECAM_SIZE);
+    assert_reserved(map, 0xFEC0_0000, 0x1000); // IOAPIC
+    assert_reserved(map, 0xFED0_0000, 0x400); // HPET
+    assert_reserved(map, 0xFED4_0000, 0x5000); // TPM CRB
+    assert_reserved(map, 0xFEE0_0000, 0x1000); // LAPIC
+    assert_reserved(map, m.vbe_lfb_base(), 0x1000);
+    assert_eq!(m.e820_ram_mmio_overlaps(), vec![]);
+}
+
+#[test]
+fn ram_above_the_hole_is_clamped_for_legacy_sizing_calls() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: PCIE_ECAM_BASE + 16 * 1024 * 1024,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let sizing = run_sizing_stub(&mut m);
+
+    assert_eq!(sizing.e820, e820_from_memory_map(&m.guest_memory_map()));
+    assert_coherent(&sizing);
+    // AH=88h stops at 63MiB; E801h only counts RAM below the ECAM window.
+    assert_eq!(sizing.ah88_kb, 0xFC00);
+    assert_eq!(sizing.e801[0], 0x3C00);
+    assert_eq!(
+        u64::from(sizing.e801[1]),
+        (PCIE_ECAM_BASE - SIXTEEN_MIB) / 0x1_0000
+    );
+    assert!(sizing
+        .e820
+        .iter()
+        .any(|e| e.region_type == E820_RAM && e.base == FOUR_GIB));
+    assert_reserved(&sizing.e820, PCIE_ECAM_BASE, PCIE_ECAM_SIZE);
+    let hole_start = PCIE_ECAM_BASE + PCIE_ECAM_SIZE;
+    assert_reserved(&sizing.e820, hole_start, FOUR_GIB - hole_start);
+    assert_eq!(m.e820_ram_mmio_overlaps(), vec![]);
+}
@@ -443,12 +443,12 @@ fn bios_vbe_sync_mode_and_lfb_base() {
                 assert_eq!(vga.get_resolution(), (w, h));
             }
 
-            m.display_present();
+            m.display_present(true);
             assert_eq!(m.display_resolution(), (w, h));
 
             // Write a single red pixel at (0,0) in packed 32bpp BGRX.
             m.write_physical_u32(m.vbe_lfb_base(), 0x00FF_0000);
-            m.display_present();
+            m.display_present(true);
             assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
         }
     }
@@ -634,7 +634,7 @@ fn bios_vbe_palette_sync_updates_vga_dac() {
 
         // Write palette index 1 to the first pixel in the 8bpp framebuffer.
         m.write_physical_u8(m.vbe_lfb_base(), 1);
-        m.display_present();
+        m.display_present(true);
         let pixel0 = m.display_framebuffer()[0];
 
         // Palette entry 1 was set to red (B=0,G=0,R=0x3F).
@@ -677,7 +677,7 @@ fn bios_vbe_palette_sync_accepts_8bit_input_in_6bit_mode() {
         // Write palette index 1 to the first pixel in the 8bpp framebuffer.
         m.write_physical_u8(m.vbe_lfb_base(), 1);
 
-        m.display_present();
+        m.display_present(true);
         let pixel0 = m.display_framebuffer()[0];
 
         // Palette entry 1 was set via `4F09h set` with an 8-bit component value 0xAA in 6-bit DAC mode.
@@ -732,7 +732,7 @@ fn bios_vbe_failed_mode_set_does_not_clear_existing_framebuffer() {
         m.write_physical_u16(0x0500, 0xDEAD);
         run_until_halt(&mut m);
 
-        m.display_present();
+        m.display_present(true);
         assert_eq!(m.display_resolution(), (1024, 768));
         assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 
@@ -48,7 +48,7 @@ fn boots_bootsector_fixture_and_validates_vga_mode13_and_serial_output() {
         "serial output did not contain expected substring: {serial_str:?}"
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     let fb = m.display_framebuffer();
     let w = 320usize;
@@ -63,11 +63,12 @@ fn boots_bootsector_fixture_and_validates_vga_mode13_and_serial_output() {
     // - palette index 0x11 in the top half, and
     // - palette index 0x22 in the bottom half.
     //
-    // In the default VGA 6x6x6 color cube palette:
-    // - 0x11 = (r=0, g=0, b=1) => RGB(0, 0, 51)
-    // - 0x22 = (r=0, g=3, b=0) => RGB(0, 153, 0)
-    let expected_top = rgba(0x00, 0x00, 0x33);
-    let expected_bottom = rgba(0x00, 0x99, 0x00);
+    // In the default VGA 6x6x6 color cube palette (stored as 6-bit DAC values and expanded with
+    // `vga_6bit_to_8bit`):
+    // - 0x11 = (r=0, g=0, b=1) => DAC (0, 0, 12) => RGB(0, 0, 48)
+    // - 0x22 = (r=0, g=3, b=0) => DAC (0, 38, 0) => RGB(0, 154, 0)
+    let expected_top = rgba(0x00, 0x00, 0x30);
+    let expected_bottom = rgba(0x00, 0x9A, 0x00);
 
     assert_eq!(top_left, expected_top);
     assert_eq!(top_right, expected_top);
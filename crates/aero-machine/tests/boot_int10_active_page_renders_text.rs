@@ -76,7 +76,7 @@ fn boot_int10_active_page_select_makes_page_visible_via_crtc_start_address() {
     m.reset();
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     let pixel0 = m.display_framebuffer()[0];
 
     // The guest wrote a space with a blue background into the top-left cell of page 1. Without
@@ -80,7 +80,7 @@ fn boot_sector_int10_aerogpu_mode13h_write_pixel_is_visible() {
 
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     let fb = m.display_framebuffer();
     let pixel = fb[(y as usize) * 320 + (x as usize)];
@@ -70,7 +70,7 @@ fn boot_int10_aerogpu_mode13h_no_clear_preserves_vram() {
     run_until_halt(&mut m);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyVga);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     // Default VGA palette entry 4 is EGA red (0xAA,0x00,0x00).
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00AA);
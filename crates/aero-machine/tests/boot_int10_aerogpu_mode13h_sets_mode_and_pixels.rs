@@ -75,7 +75,7 @@ fn boot_sector_int10_aerogpu_mode13h_sets_mode_and_pixel_is_visible() {
     m.reset();
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
 
     // Palette index 4 in the default VGA palette is EGA red: RGB(0xAA, 0x00, 0x00).
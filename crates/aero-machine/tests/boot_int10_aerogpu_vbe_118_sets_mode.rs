@@ -79,7 +79,7 @@ fn boot_int10_aerogpu_vbe_118_sets_mode_and_lfb_is_visible_via_bar1() {
     // Write a red pixel at (0,0) in VBE packed-pixel B,G,R,X format.
     m.write_physical_u32(lfb_base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
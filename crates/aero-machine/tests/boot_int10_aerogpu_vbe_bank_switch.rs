@@ -126,7 +126,7 @@ fn boot_int10_aerogpu_vbe_bank_switch_maps_a0000_window_beyond_64k() {
         "expected BGRX red pixel at LFB+0x10000"
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[16 * 1024], 0xFF00_00FF);
 }
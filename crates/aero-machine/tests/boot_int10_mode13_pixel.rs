@@ -75,7 +75,7 @@ fn boot_sector_int10_mode13h_write_pixel_is_visible() {
     run_until_halt(&mut m);
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyVga);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     let pixel = m.display_framebuffer()[(y as usize) * 320 + (x as usize)];
 
@@ -95,7 +95,7 @@ fn boot_int10_mode13h_write_pixel_service_is_visible() {
 
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
 
     // Palette index 4 in the default VGA palette is EGA red: RGB(0xAA, 0x00, 0x00).
@@ -72,7 +72,7 @@ fn boot_sector_int10_mode13h_sets_live_vga_mode_and_pixel_is_visible() {
     run_until_halt(&mut m);
 
     assert_eq!(m.active_scanout_source(), ScanoutSource::LegacyVga);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (320, 200));
     // Palette index 4 in the default VGA palette is EGA red: RGB(0xAA, 0x00, 0x00).
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00AA);
@@ -59,12 +59,12 @@ fn boot_int10_vbe_115_sets_mode_and_lfb_is_visible() {
 
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (800, 600));
 
     // Write a red pixel at (0,0) in VBE packed-pixel B,G,R,X format.
     let base = m.vbe_lfb_base();
     m.write_physical_u32(base, 0x00FF_0000);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -107,7 +107,7 @@ fn boot_int10_vbe_bank_switch_maps_a0000_window_beyond_64k() {
     // For 1024x768x32, the VRAM byte offset 0x1_0000 corresponds to:
     //   pixel_index = 0x1_0000 / 4 = 0x4000 = 16384
     //   (x, y) = (0, 16) since stride is 1024 pixels.
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[16 * 1024], 0xFF00_00FF);
 }
@@ -116,7 +116,7 @@ fn run_banked_window_pixel_test(enable_pc_platform: bool) {
 
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
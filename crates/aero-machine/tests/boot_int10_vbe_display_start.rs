@@ -116,7 +116,7 @@ fn boot_int10_vbe_display_start() {
         let y_off = m.io_read(VBE_DISPI_DATA_PORT, 2) as u16;
         assert_eq!((x_off, y_off), (1, 0));
 
-        m.display_present();
+        m.display_present(true);
         assert_eq!(m.display_resolution(), (1024, 768));
         // The guest wrote red at (0,0) and green at (1,0), then panned to x=1.
         // The top-left visible pixel should therefore be green.
@@ -202,7 +202,7 @@ fn boot_int10_vbe_logical_scanline_updates_stride_and_renderer_uses_it() {
 
     run_until_halt(&mut m);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[1024], 0xFF00_00FF);
 }
@@ -112,7 +112,7 @@ fn boot_sector_int10_vbe_sets_mode_and_lfb_is_visible_at_non_default_base() {
         "INT 10h AX=4F01 mode info PhysBasePtr must match Machine::vbe_lfb_base()"
     );
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
 
     let base = u64::from(lfb_base);
@@ -121,6 +121,6 @@ fn boot_sector_int10_vbe_sets_mode_and_lfb_is_visible_at_non_default_base() {
     m.write_physical_u8(base + 2, 0xFF); // R
     m.write_physical_u8(base + 3, 0x00); // X
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
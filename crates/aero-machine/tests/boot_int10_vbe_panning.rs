@@ -120,7 +120,7 @@ fn boot_int10_vbe_scanline_bytes_and_display_start_affect_scanout_base() {
     m.write_physical_u32(base + wrong_off, 0x00FF_0000); // red (BGRX)
     m.write_physical_u32(base + correct_off, 0x0000_FF00); // green (BGRX)
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_FF00);
 }
@@ -106,7 +106,7 @@ fn assert_vbe_mode_set_and_lfb_visible(vbe_mode_with_flags: u16, expected_res: (
             "VBE set-mode should return AX=0x004F (success)"
         );
 
-        m.display_present();
+        m.display_present(true);
         assert_eq!(m.display_resolution(), expected_res);
 
         let phys_base_ptr = m.read_physical_u32(0x0500 + 40);
@@ -121,7 +121,7 @@ fn assert_vbe_mode_set_and_lfb_visible(vbe_mode_with_flags: u16, expected_res: (
         );
         m.write_physical_u32(base, 0x00FF_0000);
 
-        m.display_present();
+        m.display_present(true);
         assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
     }
 }
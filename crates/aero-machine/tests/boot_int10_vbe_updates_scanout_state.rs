@@ -392,6 +392,8 @@ fn boot_sector_int10_vbe_does_not_override_wddm_scanout_state() {
         height: 600,
         pitch_bytes: 800 * 4,
         format: SCANOUT_FORMAT_B8G8R8X8,
+        slot0_offset: 0,
+        slot1_offset: 0,
     });
     let generation_before = scanout_state.snapshot().generation;
 
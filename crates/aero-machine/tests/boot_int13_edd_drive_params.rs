@@ -0,0 +1,234 @@
+use aero_devices::pci::profile::{IDE_PIIX3, SATA_AHCI_ICH9};
+use aero_devices::pci::PciBdf;
+use aero_devices_storage::ata::AtaDrive;
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_storage::{MemBackend, RawDisk};
+use pretty_assertions::assert_eq;
+
+const DISK_SECTORS: u64 = 16 * 1024;
+const ISO_SECTORS: u64 = 64;
+
+/// Register dumps written by the boot stub.
+const AH41_RESULT: u64 = 0x0500;
+const AH48_FLAGS: u64 = 0x0508;
+const AH08_RESULT: u64 = 0x0510;
+
+const HDD_TABLE_V3_T13: u16 = 0x0600;
+const CD_TABLE_V3: u16 = 0x0680;
+const HDD_TABLE_V2: u16 = 0x0700;
+const HDD_TABLE_SHORT: u16 = 0x0780;
+
+/// `(drive, table, buffer size)` for each AH=48h call, in order; FLAGS land at `AH48_FLAGS + 2*i`.
+const AH48_CALLS: [(u8, u16, u16); 4] = [
+    (0x80, HDD_TABLE_V3_T13, 0x50),
+    (0xE0, CD_TABLE_V3, 0x42),
+    (0x80, HDD_TABLE_V2, 0x41),
+    (0x80, HDD_TABLE_SHORT, 0x19),
+];
+
+fn boot_sector() -> Vec<u8> {
+    let mut code = vec![
+        0xFA, // cli
+        0x31, 0xC0, // xor ax, ax
+        0x8E, 0xD8, // mov ds, ax
+        // AH=41h installation check on drive 0x80.
+        0xB8, 0x00, 0x41, // mov ax, 0x4100
+        0xBB, 0xAA, 0x55, // mov bx, 0x55AA
+        0xBA, 0x80, 0x00, // mov dx, 0x0080
+        0xCD, 0x13, // int 0x13
+        0x9C, // pushf
+        0xA3, 0x00, 0x05, // mov [0x0500], ax
+        0x89, 0x1E, 0x02, 0x05, // mov [0x0502], bx
+        0x89, 0x0E, 0x04, 0x05, // mov [0x0504], cx
+        0x58, // pop ax
+        0xA3, 0x06, 0x05, // mov [0x0506], ax
+    ];
+    for (i, (drive, table, size)) in AH48_CALLS.into_iter().enumerate() {
+        let [table_lo, table_hi] = table.to_le_bytes();
+        let [size_lo, size_hi] = size.to_le_bytes();
+        let [slot_lo, slot_hi] = (AH48_FLAGS as u16 + 2 * i as u16).to_le_bytes();
+        code.extend_from_slice(&[
+            0xC7, 0x06, table_lo, table_hi, size_lo, size_hi, // mov word [table], size
+            0xBE, table_lo, table_hi, // mov si, table
+            0xB8, 0x00, 0x48, // mov ax, 0x4800
+            0xBA, drive, 0x00, // mov dx, drive
+            0xCD, 0x13, // int 0x13
+            0x9C, // pushf
+            0x58, // pop ax
+            0xA3, slot_lo, slot_hi, // mov [slot], ax
+        ]);
+    }
+    code.extend_from_slice(&[
+        // AH=08h legacy drive parameters on drive 0x80.
+        0xB4, 0x08, // mov ah, 0x08
+        0xBA, 0x80, 0x00, // mov dx, 0x0080
+        0xCD, 0x13, // int 0x13
+        0x89, 0x0E, 0x10, 0x05, // mov [0x0510], cx
+        0x89, 0x16, 0x12, 0x05, // mov [0x0512], dx
+        0xF4, // hlt
+    ]);
+
+    let mut disk = vec![0u8; DISK_SECTORS as usize * aero_storage::SECTOR_SIZE];
+    disk[..code.len()].copy_from_slice(&code);
+    disk[510] = 0x55;
+    disk[511] = 0xAA;
+    disk
+}
+
+fn run(cfg: MachineConfig) -> Machine {
+    let mut m = Machine::new(cfg).unwrap();
+    m.set_disk_image(boot_sector()).unwrap();
+    m.attach_install_media_iso_bytes(vec![0u8; ISO_SECTORS as usize * 2048])
+        .unwrap();
+    m.reset();
+    // Poison the result tables so bytes beyond the returned length are detectable.
+    m.write_physical(u64::from(HDD_TABLE_V3_T13), &[0xCC; 0x200]);
+
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return m,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("boot stub did not halt");
+}
+
+/// Build an expected EDD 3.0 (0x42/0x4A) AH=48h table: the EDD 2.x head, then the device path
+/// information with the interface path `[bus, device, function, channel]` and first device path
+/// byte, followed by the checksum.
+#[allow(clippy::too_many_arguments)]
+fn edd_table(
+    len: u16,
+    flags: u16,
+    chs: [u32; 3],
+    sectors: u64,
+    bytes_per_sector: u16,
+    interface: &[u8; 8],
+    pci: Option<(PciBdf, u8)>,
+    device: u8,
+) -> Vec<u8> {
+    let mut t = Vec::new();
+    t.extend_from_slice(&len.to_le_bytes());
+    t.extend_from_slice(&flags.to_le_bytes());
+    for v in chs {
+        t.extend_from_slice(&v.to_le_bytes());
+    }
+    t.extend_from_slice(&sectors.to_le_bytes());
+    t.extend_from_slice(&bytes_per_sector.to_le_bytes());
+    t.extend_from_slice(&u32::MAX.to_le_bytes()); // no DPTE
+    t.extend_from_slice(&0xBEDDu16.to_le_bytes());
+    t.extend_from_slice(&[(len - 0x1E) as u8, 0, 0, 0]);
+    t.extend_from_slice(b"PCI ");
+    t.extend_from_slice(interface);
+    match pci {
+        Some((bdf, channel)) => t.extend_from_slice(&[bdf.bus, bdf.device, bdf.function, channel]),
+        None => t.extend_from_slice(&[0; 4]),
+    }
+    t.extend_from_slice(&[0; 4]);
+    let device_path_len = if len == 0x4A { 16 } else { 8 };
+    let mut device_path = vec![0u8; device_path_len];
+    device_path[0] = device;
+    t.extend_from_slice(&device_path);
+    t.push(0); // reserved
+    let sum = t[0x1E..].iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+    t.push(0u8.wrapping_sub(sum));
+    assert_eq!(t.len(), len as usize);
+    t
+}
+
+fn read_table(m: &mut Machine, table: u16, len: usize) -> Vec<u8> {
+    m.read_physical_bytes(u64::from(table), len)
+}
+
+/// `table` followed by one untouched poison byte.
+fn poisoned(table: &[u8]) -> Vec<u8> {
+    let mut out = table.to_vec();
+    out.push(0xCC);
+    out
+}
+
+#[test]
+fn int13_edd30_drive_params_report_controller_device_paths() {
+    let mut m = run(MachineConfig::win7_storage(16 * 1024 * 1024));
+
+    // AH=41h: EDD 3.0, disk access + EDD subsets.
+    assert_eq!(m.read_physical_u16(AH41_RESULT) >> 8, 0x30);
+    assert_eq!(m.read_physical_u16(AH41_RESULT + 2), 0xAA55);
+    assert_eq!(m.read_physical_u16(AH41_RESULT + 4), 0x0005);
+    assert_eq!(m.read_physical_u16(AH41_RESULT + 6) & 1, 0);
+
+    let cf: Vec<u16> = (0..AH48_CALLS.len() as u64)
+        .map(|i| m.read_physical_u16(AH48_FLAGS + 2 * i) & 1)
+        .collect();
+    assert_eq!(cf, vec![0, 0, 0, 1]);
+
+    // The fixed disk sits on AHCI port 0 (a single-channel controller).
+    let hdd = edd_table(
+        0x4A,
+        0x0002, // CHS valid
+        [16, 16, 63],
+        DISK_SECTORS,
+        512,
+        b"SATA    ",
+        Some((SATA_AHCI_ICH9.bdf, 0xFF)),
+        0,
+    );
+    assert_eq!(read_table(&mut m, HDD_TABLE_V3_T13, 0x4B), poisoned(&hdd));
+
+    // The install media is the ATAPI master on the IDE secondary channel.
+    let cd = edd_table(
+        0x42,
+        0x0004, // removable
+        [0, 0, 0],
+        ISO_SECTORS,
+        2048,
+        b"ATAPI   ",
+        Some((IDE_PIIX3.bdf, 1)),
+        0,
+    );
+    assert_eq!(read_table(&mut m, CD_TABLE_V3, 0x43), poisoned(&cd));
+
+    // A buffer too small for EDD 3.0 gets the 2.x table.
+    let mut v2 = hdd[..0x1E].to_vec();
+    v2[0] = 0x1E;
+    assert_eq!(read_table(&mut m, HDD_TABLE_V2, 0x1F), poisoned(&v2));
+
+    // A buffer below the EDD 1.x size fails without touching the caller's table.
+    assert_eq!(
+        read_table(&mut m, HDD_TABLE_SHORT, 4),
+        vec![0x19, 0x00, 0xCC, 0xCC]
+    );
+
+    // Geometry matches the controller's IDENTIFY DEVICE data and AH=08h heads/sectors.
+    let disk = RawDisk::create(MemBackend::new(), DISK_SECTORS * 512).unwrap();
+    let drive = AtaDrive::new(Box::new(disk)).unwrap();
+    let identify = drive.identify_sector();
+    let word = |i: usize| u32::from(u16::from_le_bytes([identify[2 * i], identify[2 * i + 1]]));
+    assert_eq!([word(1), word(3), word(6)], [16, 16, 63]);
+
+    let cx = m.read_physical_u16(AH08_RESULT);
+    let dx = m.read_physical_u16(AH08_RESULT + 2);
+    assert_eq!(u32::from(dx >> 8) + 1, word(3));
+    assert_eq!(u32::from(cx & 0x3F), word(6));
+}
+
+#[test]
+fn int13_edd30_drive_params_without_known_controller_zero_the_paths() {
+    let mut m = run(MachineConfig {
+        enable_ahci: false,
+        ..MachineConfig::win7_storage(16 * 1024 * 1024)
+    });
+
+    let hdd = edd_table(
+        0x4A,
+        0x0002,
+        [16, 16, 63],
+        DISK_SECTORS,
+        512,
+        b"ATA     ",
+        None,
+        0,
+    );
+    assert_eq!(read_table(&mut m, HDD_TABLE_V3_T13, 0x4A), hdd);
+}
@@ -0,0 +1,243 @@
+use aero_devices::pci::profile;
+use aero_machine::{BootStage, Machine, MachineConfig, RunExit};
+use aero_protocol::aerogpu::aerogpu_pci as agpu_pci;
+use pretty_assertions::assert_eq;
+
+/// Boot sector that behaves like a (very) small boot loader + kernel:
+/// - INT 13h AH=02h reads LBA 1 to 0000:8000,
+/// - switches to 32-bit protected mode,
+/// - writes the host bridge PCI command register via config mechanism #1 from ring 0,
+/// - halts.
+fn build_boot_stage_boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    let mut i = 0usize;
+
+    let mut emit = |bytes: &[u8]| {
+        sector[i..i + bytes.len()].copy_from_slice(bytes);
+        i += bytes.len();
+        i
+    };
+
+    emit(&[0xFA]); // cli
+    emit(&[0x31, 0xC0]); // xor ax, ax
+    emit(&[0x8E, 0xD8]); // mov ds, ax
+    emit(&[0x8E, 0xC0]); // mov es, ax
+
+    // INT 13h AH=02h: read 1 sector (CHS 0/0/2) from drive 0x80 into ES:BX = 0000:8000.
+    emit(&[0xB8, 0x01, 0x02]); // mov ax, 0x0201
+    emit(&[0xB9, 0x02, 0x00]); // mov cx, 0x0002
+    emit(&[0xBA, 0x80, 0x00]); // mov dx, 0x0080
+    emit(&[0xBB, 0x00, 0x80]); // mov bx, 0x8000
+    emit(&[0xCD, 0x13]); // int 0x13
+
+    // lgdt [gdtr] (disp16 patched below)
+    let gdtr_disp_pos = emit(&[0x0F, 0x01, 0x16]);
+    emit(&[0x00, 0x00]);
+    emit(&[0x66, 0x0F, 0x20, 0xC0]); // mov eax, cr0
+    emit(&[0x66, 0x83, 0xC8, 0x01]); // or eax, 1
+    emit(&[0x66, 0x0F, 0x22, 0xC0]); // mov cr0, eax
+
+    // jmp 0x08:prot_entry (offset patched below)
+    let far_off_pos = emit(&[0xEA]);
+    let prot_entry = emit(&[0x00, 0x00, 0x08, 0x00]);
+
+    // 32-bit protected mode, CPL0.
+    emit(&[0x66, 0xB8, 0x10, 0x00]); // mov ax, 0x10
+    emit(&[0x8E, 0xD8]); // mov ds, ax
+    emit(&[0x8E, 0xD0]); // mov ss, ax
+    emit(&[0xB8, 0x04, 0x00, 0x00, 0x80]); // mov eax, 0x8000_0004 (00:00.0, COMMAND)
+    emit(&[0x66, 0xBA, 0xF8, 0x0C]); // mov dx, 0xCF8
+    emit(&[0xEF]); // out dx, eax
+    emit(&[0x66, 0xBA, 0xFC, 0x0C]); // mov dx, 0xCFC
+    emit(&[0x66, 0xB8, 0x06, 0x00]); // mov ax, 0x0006
+    emit(&[0x66, 0xEF]); // out dx, ax
+    let code_end = emit(&[0xF4]); // hlt
+
+    // Flat 4GiB code (0x08) and data (0x10) descriptors, followed by the GDTR.
+    let gdt_offset = code_end.next_multiple_of(8);
+    let gdtr_offset = gdt_offset + 24;
+    sector[gdt_offset + 8..gdt_offset + 16]
+        .copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0x00, 0x9A, 0xCF, 0x00]);
+    sector[gdt_offset + 16..gdt_offset + 24]
+        .copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0x00, 0x92, 0xCF, 0x00]);
+    sector[gdtr_offset..gdtr_offset + 2].copy_from_slice(&23u16.to_le_bytes());
+    sector[gdtr_offset + 2..gdtr_offset + 6]
+        .copy_from_slice(&(0x7C00u32 + gdt_offset as u32).to_le_bytes());
+
+    sector[gdtr_disp_pos..gdtr_disp_pos + 2]
+        .copy_from_slice(&(0x7C00u16 + gdtr_offset as u16).to_le_bytes());
+    sector[far_off_pos..far_off_pos + 2]
+        .copy_from_slice(&(0x7C00u16 + prot_entry as u16).to_le_bytes());
+
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+fn claim_wddm_scanout(m: &mut Machine) {
+    let bdf = profile::AEROGPU.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, profile::AEROGPU_BAR0_INDEX)
+        .expect("BAR0 should be assigned");
+    let fb = m
+        .pci_bar_base(bdf, profile::AEROGPU_BAR1_VRAM_INDEX)
+        .expect("BAR1 should be assigned");
+
+    let regs = [
+        (agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH, 800),
+        (agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_HEIGHT, 600),
+        (
+            agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_FORMAT,
+            agpu_pci::AerogpuFormat::B8G8R8X8Unorm as u32,
+        ),
+        (agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_PITCH_BYTES, 800 * 4),
+        (agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_LO, fb as u32),
+        (
+            agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_HI,
+            (fb >> 32) as u32,
+        ),
+        (agpu_pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE, 1),
+    ];
+    for (reg, value) in regs {
+        m.write_physical_u32(bar0 + u64::from(reg), value);
+    }
+    m.process_aerogpu();
+}
+
+fn transmit_one_e1000_frame(m: &mut Machine) {
+    let bdf = profile::NIC_E1000_82540EM.bdf;
+    let pci_cfg = m.pci_config_ports().unwrap();
+    let mmio_base = {
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        let cmd = pci_cfg.bus_mut().read_config(bdf, 0x04, 2);
+        pci_cfg.bus_mut().write_config(bdf, 0x04, 2, cmd | (1 << 2));
+        u64::from(pci_cfg.bus_mut().read_config(bdf, 0x10, 4) & 0xFFFF_FFF0)
+    };
+
+    let (ring_base, pkt_base) = (0x1000u64, 0x2000u64);
+    m.write_physical(pkt_base, &[0x11; 60]);
+    let mut desc = [0u8; 16];
+    desc[0..8].copy_from_slice(&pkt_base.to_le_bytes());
+    desc[8..10].copy_from_slice(&60u16.to_le_bytes());
+    desc[11] = 0b0000_1001; // EOP | RS
+    m.write_physical(ring_base, &desc);
+
+    m.write_physical_u32(mmio_base + 0x3800, ring_base as u32); // TDBAL
+    m.write_physical_u32(mmio_base + 0x3808, 16 * 4); // TDLEN
+    m.write_physical_u32(mmio_base + 0x0400, 1 << 1); // TCTL.EN
+    m.write_physical_u32(mmio_base + 0x3818, 1); // TDT
+    m.poll_network();
+}
+
+#[test]
+fn scripted_boot_walks_through_boot_stages_in_order() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        enable_e1000: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let mut disk = build_boot_stage_boot_sector().to_vec();
+    disk.resize(2 * aero_storage::SECTOR_SIZE, 0xA5);
+    m.set_disk_image(disk).unwrap();
+    m.reset();
+
+    assert_eq!(m.boot_stage(), BootStage::PostComplete);
+    let changes_after_post = m.boot_stage_changes();
+
+    // Idle polling must not advance anything on its own.
+    m.process_aerogpu();
+    m.poll_network();
+    assert_eq!(m.boot_stage(), BootStage::PostComplete);
+    assert_eq!(m.boot_stage_changes(), changes_after_post);
+
+    run_until_halt(&mut m);
+    assert_eq!(
+        m.read_physical_u8(0x8000),
+        0xA5,
+        "INT 13h read should succeed"
+    );
+    assert_eq!(m.boot_stage(), BootStage::DeviceEnumeration);
+    assert_eq!(m.boot_stage_changes(), changes_after_post + 3);
+
+    claim_wddm_scanout(&mut m);
+    assert_eq!(m.boot_stage(), BootStage::WddmScanout);
+
+    transmit_one_e1000_frame(&mut m);
+    assert_eq!(m.boot_stage(), BootStage::NetworkActive);
+    assert_eq!(m.boot_stage_changes(), changes_after_post + 5);
+
+    let transitions = m.boot_stage_transitions();
+    assert_eq!(
+        transitions.iter().map(|t| t.stage).collect::<Vec<_>>(),
+        vec![
+            BootStage::PostComplete,
+            BootStage::Bootloader,
+            BootStage::KernelStarting,
+            BootStage::DeviceEnumeration,
+            BootStage::WddmScanout,
+            BootStage::NetworkActive,
+        ]
+    );
+    assert!(transitions
+        .windows(2)
+        .all(|w| w[0].guest_time_ns <= w[1].guest_time_ns));
+
+    // A reset through POST starts a new boot.
+    m.reset();
+    assert_eq!(m.boot_stage(), BootStage::PostComplete);
+    assert_eq!(m.boot_stage_transitions().len(), 1);
+    assert!(m.boot_stage_changes() > changes_after_post + 5);
+}
+
+#[test]
+fn later_stages_are_not_undone_by_earlier_events() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        enable_e1000: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Network activity before the display driver claims scanout skips `WddmScanout`.
+    transmit_one_e1000_frame(&mut m);
+    assert_eq!(m.boot_stage(), BootStage::NetworkActive);
+    let changes = m.boot_stage_changes();
+
+    claim_wddm_scanout(&mut m);
+    assert_eq!(m.boot_stage(), BootStage::NetworkActive);
+    assert_eq!(m.boot_stage_changes(), changes);
+    assert_eq!(
+        m.boot_stage_transitions()
+            .iter()
+            .map(|t| t.stage)
+            .collect::<Vec<_>>(),
+        vec![BootStage::PostComplete, BootStage::NetworkActive]
+    );
+}
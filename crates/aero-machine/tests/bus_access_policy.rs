@@ -0,0 +1,106 @@
+use aero_devices::a20_gate::A20_GATE_PORT;
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_devices::pci::{PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig};
+use pretty_assertions::assert_eq;
+
+const VBE_INDEX: u16 = 0x01CE;
+const VBE_DATA: u16 = 0x01CF;
+
+fn machine() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: true,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.reset();
+    // Fast A20 gate at port 0x92: the HPET window aliases the IOAPIC with A20 masked.
+    m.io_write(A20_GATE_PORT, 1, 0x02);
+    m
+}
+
+#[test]
+fn vbe_dispi_ports_accept_only_16bit_accesses() {
+    let mut m = machine();
+
+    // VBE_DISPI_INDEX_XRES.
+    m.io_write(VBE_INDEX, 2, 0x0001);
+    m.io_write(VBE_DATA, 2, 640);
+    assert_eq!(m.io_read(VBE_DATA, 2), 640);
+
+    // Byte and dword accesses never reach the device.
+    m.io_write(VBE_INDEX, 1, 0x02);
+    m.io_write(VBE_DATA, 4, 0x1234_5678);
+    assert_eq!(m.io_read(VBE_INDEX, 2), 0x0001);
+    assert_eq!(m.io_read(VBE_DATA, 2), 640);
+    assert_eq!(m.io_read(VBE_DATA, 1), 0xFF);
+    assert_eq!(m.io_read(VBE_INDEX, 4), 0xFFFF_FFFF);
+}
+
+#[test]
+fn vga_port_word_accesses_are_split_into_index_and_data_bytes() {
+    let mut m = machine();
+
+    // `out dx, ax` to the sequencer: index 0x02 (map mask), data 0x0A.
+    m.io_write(0x3C4, 2, 0x0A02);
+    m.io_write(0x3C4, 1, 0x02);
+    assert_eq!(m.io_read(0x3C5, 1), 0x0A);
+    assert_eq!(m.io_read(0x3C4, 2), 0x0A02);
+
+    // Graphics controller via a dword write: index 0x05 (mode), data 0x40; the upper half lands on
+    // the following CRTC-less ports one byte at a time and is ignored there.
+    m.io_write(0x3CE, 4, 0x0000_4005);
+    m.io_write(0x3CE, 1, 0x05);
+    assert_eq!(m.io_read(0x3CF, 1), 0x40);
+}
+
+#[test]
+fn hpet_rejects_narrow_and_misaligned_accesses() {
+    let mut m = machine();
+
+    let caps = m.read_physical_u64(HPET_MMIO_BASE);
+    assert_ne!(caps, u64::MAX);
+    assert_eq!(m.read_physical_u32(HPET_MMIO_BASE), caps as u32);
+    assert_eq!(m.read_physical_u32(HPET_MMIO_BASE + 4), (caps >> 32) as u32);
+
+    assert_eq!(m.read_physical_u8(HPET_MMIO_BASE), 0xFF);
+    assert_eq!(m.read_physical_u16(HPET_MMIO_BASE + 4), 0xFFFF);
+    assert_eq!(m.read_physical_u32(HPET_MMIO_BASE + 2), 0xFFFF_FFFF);
+    assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 4), u64::MAX);
+
+    // A byte write to the general configuration register (ENABLE_CNF) is dropped.
+    m.write_physical_u8(HPET_MMIO_BASE + 0x10, 0x01);
+    assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 0x10) & 1, 0);
+    m.write_physical_u32(HPET_MMIO_BASE + 0x10, 0x01);
+    assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 0x10) & 1, 1);
+}
+
+#[test]
+fn pci_config_data_reads_straddling_the_window_end_are_split() {
+    let mut m = machine();
+
+    // Host bridge 00:00.0, vendor/device ID dword.
+    m.io_write(PCI_CFG_ADDR_PORT, 4, 0x8000_0000);
+    let id = m.io_read(PCI_CFG_DATA_PORT, 4);
+    assert_ne!(id, 0xFFFF_FFFF);
+
+    let beyond = m.io_read(PCI_CFG_DATA_PORT + 4, 2);
+    assert_eq!(
+        m.io_read(PCI_CFG_DATA_PORT + 2, 4),
+        (id >> 16) | (beyond << 16)
+    );
+    // A word straddling the address and data dwords reads one byte of each.
+    assert_eq!(
+        m.io_read(PCI_CFG_DATA_PORT - 1, 2),
+        0x80 | ((id & 0xFF) << 8)
+    );
+}
@@ -0,0 +1,135 @@
+use aero_machine::{Machine, MachineConfig, MachineError, RunExit};
+use pretty_assertions::assert_eq;
+
+/// Stores a guest setting in CMOS byte 0x40, clobbers the extended memory size (0x17), then halts.
+const WRITE_CMOS: [u8; 20] = [
+    0xFA, // cli
+    0xB0, 0x40, // mov al, 0x40
+    0xE6, 0x70, // out 0x70, al
+    0xB0, 0x5A, // mov al, 0x5A
+    0xE6, 0x71, // out 0x71, al
+    0xB0, 0x17, // mov al, 0x17
+    0xE6, 0x70, // out 0x70, al
+    0xB0, 0xFF, // mov al, 0xFF
+    0xE6, 0x71, // out 0x71, al
+    0xF4, // hlt
+    0xEB, 0xFD, // jmp hlt
+];
+
+const HALT: [u8; 3] = [
+    0xFA, // cli
+    0xF4, // hlt
+    0x90, // nop
+];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn config(ram_size_bytes: u64, initial_nvram: Option<Vec<u8>>) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes,
+        enable_pc_platform: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        initial_nvram,
+        ..Default::default()
+    }
+}
+
+fn new_machine(cfg: MachineConfig, code: &[u8]) -> Machine {
+    let mut m = Machine::new(cfg).unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest never reached HLT");
+}
+
+fn cmos(m: &mut Machine, idx: u8) -> u8 {
+    m.io_write(0x70, 1, u32::from(idx));
+    m.io_read(0x71, 1) as u8
+}
+
+fn cmos_write(m: &mut Machine, idx: u8, val: u8) {
+    m.io_write(0x70, 1, u32::from(idx));
+    m.io_write(0x71, 1, u32::from(val));
+}
+
+fn cmos_u16(m: &mut Machine, lo: u8) -> u16 {
+    u16::from_le_bytes([cmos(m, lo), cmos(m, lo + 1)])
+}
+
+fn assert_checksum_valid(m: &mut Machine) {
+    let sum: u16 = (0x10..0x2E).map(|idx| u16::from(cmos(m, idx))).sum();
+    assert_eq!(u16::from_be_bytes([cmos(m, 0x2E), cmos(m, 0x2F)]), sum);
+}
+
+#[test]
+fn guest_cmos_bytes_survive_into_a_new_machine() {
+    let mut m = new_machine(config(2 * 1024 * 1024, None), &WRITE_CMOS);
+    run_until_halt(&mut m);
+    assert_eq!(cmos(&mut m, 0x40), 0x5A);
+    let blob = m.export_nvram();
+
+    // A fresh machine with different RAM boots with the guest's byte, but the firmware's memory
+    // sizes replace the stale (and clobbered) imported ones.
+    let mut m = new_machine(config(4 * 1024 * 1024, Some(blob)), &HALT);
+    assert_eq!(cmos(&mut m, 0x40), 0x5A);
+    assert_eq!(cmos_u16(&mut m, 0x17), 3 * 1024);
+    assert_eq!(cmos_u16(&mut m, 0x30), 3 * 1024);
+    assert_checksum_valid(&mut m);
+}
+
+#[test]
+fn cold_reset_restores_the_imported_bank() {
+    let mut source = new_machine(config(2 * 1024 * 1024, None), &WRITE_CMOS);
+    run_until_halt(&mut source);
+    let blob = source.export_nvram();
+
+    let mut m = new_machine(config(2 * 1024 * 1024, Some(blob)), &HALT);
+    cmos_write(&mut m, 0x40, 0x11);
+    m.reset();
+    assert_eq!(cmos(&mut m, 0x40), 0x5A);
+    assert_eq!(cmos_u16(&mut m, 0x17), 1024);
+    assert_checksum_valid(&mut m);
+}
+
+#[test]
+fn import_nvram_applies_immediately_and_rejects_foreign_blobs() {
+    let mut source = new_machine(config(2 * 1024 * 1024, None), &WRITE_CMOS);
+    run_until_halt(&mut source);
+    let blob = source.export_nvram();
+
+    let mut m = new_machine(config(2 * 1024 * 1024, None), &HALT);
+    assert_eq!(cmos(&mut m, 0x40), 0);
+    m.import_nvram(&blob).unwrap();
+    assert_eq!(cmos(&mut m, 0x40), 0x5A);
+    assert_eq!(cmos_u16(&mut m, 0x17), 1024);
+    assert_checksum_valid(&mut m);
+
+    assert!(matches!(
+        m.import_nvram(b"not an nvram blob"),
+        Err(MachineError::InvalidNvram(_))
+    ));
+    assert!(matches!(
+        Machine::new(config(2 * 1024 * 1024, Some(vec![0; 16]))),
+        Err(MachineError::InvalidNvram(_))
+    ));
+    // A rejected blob leaves the current contents alone.
+    assert_eq!(cmos(&mut m, 0x40), 0x5A);
+}
@@ -0,0 +1,125 @@
+use aero_machine::{Machine, MachineConfig, RomWrite, RunExit};
+use pretty_assertions::assert_eq;
+
+/// Patches the immediate of the following `mov al, 0x00` to 0x42, stores AL at 0x500 and halts.
+const SELF_PATCH: [u8; 18] = [
+    0xFA, // cli
+    0x31, 0xC0, // xor ax, ax
+    0x8E, 0xD8, // mov ds, ax
+    0xC6, 0x06, 0x0B, 0x7C, 0x42, // mov byte [0x7C0B], 0x42
+    0xB0, 0x00, // mov al, 0x00 (immediate at 0x7C0B)
+    0xA2, 0x00, 0x05, // mov [0x500], al
+    0xF4, // hlt
+    0xEB, 0xFD, // jmp hlt
+];
+
+/// Writes 0x99 to the system BIOS ROM at F000:1234, then halts.
+const ROM_WRITE: [u8; 15] = [
+    0xFA, // cli
+    0xB8, 0x00, 0xF0, // mov ax, 0xF000
+    0x8E, 0xC0, // mov es, ax
+    0x26, 0xC6, 0x06, 0x34, 0x12, 0x99, // mov byte [es:0x1234], 0x99
+    0xF4, // hlt
+    0xEB, 0xFD, // jmp hlt
+];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest never reached HLT");
+}
+
+#[test]
+fn smc_detector_reports_page_patched_and_executed_in_one_batch() {
+    let mut m = new_machine(&SELF_PATCH);
+    m.set_smc_detection(true);
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u8(0x500), 0x42);
+
+    let log = m.take_smc_log();
+    assert_eq!(log.dropped, 0);
+    assert_eq!(log.entries.len(), 1);
+    let occurrence = &log.entries[0];
+    assert_eq!(occurrence.page, 0x7000);
+    assert_eq!(occurrence.count, 1);
+    // Both the patching instruction and the patched one executed from the page.
+    assert!(occurrence.rips.contains(&0x7C05), "{occurrence:?}");
+    assert!(occurrence.rips.contains(&0x7C0A), "{occurrence:?}");
+    assert!(m.take_smc_log().entries.is_empty());
+}
+
+#[test]
+fn smc_detector_is_silent_when_disabled() {
+    let mut m = new_machine(&SELF_PATCH);
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u8(0x500), 0x42);
+    assert!(m.take_smc_log().entries.is_empty());
+}
+
+#[test]
+fn rom_write_monitor_records_dropped_rom_writes() {
+    let mut m = new_machine(&ROM_WRITE);
+    let before = m.read_physical_u8(0xF1234);
+    m.set_rom_write_monitor(true);
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u8(0xF1234), before);
+
+    let log = m.take_rom_write_log();
+    assert_eq!(log.dropped, 0);
+    assert_eq!(
+        log.entries,
+        [RomWrite {
+            paddr: 0xF1234,
+            size: 1,
+            value: 0x99,
+            rip: Some(0x7C06),
+            count: 1,
+        }]
+    );
+
+    // Host writes outside a vCPU batch carry no RIP and are deduplicated by address and size.
+    m.write_physical_u8(0xF1234, 0x11);
+    m.write_physical_u8(0xF1234, 0x22);
+    assert_eq!(
+        m.take_rom_write_log().entries,
+        [RomWrite {
+            paddr: 0xF1234,
+            size: 1,
+            value: 0x22,
+            rip: None,
+            count: 2,
+        }]
+    );
+
+    m.set_rom_write_monitor(false);
+    m.write_physical_u8(0xF1234, 0x33);
+    assert!(m.take_rom_write_log().entries.is_empty());
+}
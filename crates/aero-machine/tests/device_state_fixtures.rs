@@ -0,0 +1,214 @@
+//! Device state compatibility corpus (see `docs/16-snapshots.md`).
+//!
+//! `tests/fixtures/device_states/` holds one serialized device state per released format, named
+//! `<id>-<NAME>-v<version>.<flags>.bin`. Every blob must keep loading into the current build.
+//! Run with `AERO_UPDATE_DEVICE_STATE_FIXTURES=1` to record fixtures for the current formats;
+//! existing files are never overwritten.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use aero_machine::{Machine, MachineConfig};
+use aero_snapshot::DeviceId;
+use pretty_assertions::assert_eq;
+
+const UPDATE_ENV: &str = "AERO_UPDATE_DEVICE_STATE_FIXTURES";
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/device_states")
+}
+
+/// The machine configuration fixtures are recorded with and loaded into. Enable optional devices
+/// here to bring them into the corpus.
+fn fixture_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_e1000: true,
+        enable_virtio_net: false,
+        enable_virtio_rng: true,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn fixture_name(id: DeviceId, version: u16, flags: u16) -> String {
+    let name = id.name().unwrap_or("UNKNOWN");
+    format!("{}-{name}-v{version}.{flags}.bin", id.0)
+}
+
+/// `(id, version, flags)` of the first entry of a framed device state blob.
+fn blob_header(bytes: &[u8]) -> (DeviceId, u16, u16) {
+    assert!(bytes.len() >= 8, "truncated device state blob");
+    let id = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+    let flags = u16::from_le_bytes(bytes[6..8].try_into().unwrap());
+    (DeviceId(id), version, flags)
+}
+
+fn read_fixtures() -> Vec<(PathBuf, Vec<u8>)> {
+    let Ok(entries) = fs::read_dir(fixture_dir()) else {
+        return Vec::new();
+    };
+    let mut fixtures: Vec<(PathBuf, Vec<u8>)> = entries
+        .map(|e| e.expect("read fixture dir entry").path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "bin"))
+        .map(|p| {
+            let bytes = fs::read(&p).unwrap_or_else(|e| panic!("read {}: {e}", p.display()));
+            (p, bytes)
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+#[test]
+fn manifest_lists_every_device_entry_with_its_size() {
+    let m = fixture_machine();
+    let manifest = m.device_snapshot_manifest();
+    let ids: BTreeSet<u32> = manifest.iter().map(|d| d.id.0).collect();
+    for id in [
+        DeviceId::PLATFORM_INTERRUPTS,
+        DeviceId::PIT,
+        DeviceId::RTC,
+        DeviceId::ACPI_PM,
+        DeviceId::E1000,
+        DeviceId::VIRTIO_RNG,
+    ] {
+        assert!(
+            ids.contains(&id.0),
+            "{:?} missing from {manifest:#?}",
+            id.name()
+        );
+    }
+
+    for entry in &manifest {
+        assert_eq!(entry.name, entry.id.name());
+        assert_ne!(entry.size_bytes, 0, "{entry:?}");
+        let blob = m.save_device_state(entry.id).unwrap();
+        assert_eq!(blob_header(&blob).0, entry.id);
+    }
+
+    let e1000 = manifest.iter().find(|d| d.id == DeviceId::E1000).unwrap();
+    assert_eq!(e1000.io_device_id, Some(*b"E1K0"));
+    assert_eq!(m.save_device_state(DeviceId(0xFFFF_0000)), None);
+}
+
+#[test]
+fn every_device_state_round_trips_into_a_fresh_machine() {
+    let src = fixture_machine();
+    let ids: BTreeSet<u32> = src
+        .device_snapshot_manifest()
+        .iter()
+        .map(|d| d.id.0)
+        .collect();
+
+    for id in ids.into_iter().map(DeviceId) {
+        let blob = src.save_device_state(id).unwrap();
+        let mut dst = fixture_machine();
+        dst.load_device_state(id, &blob)
+            .unwrap_or_else(|e| panic!("{:?}: {e}", id.name()));
+
+        let versions = |m: &Machine| -> Vec<(u16, u16)> {
+            m.device_snapshot_manifest()
+                .iter()
+                .filter(|d| d.id == id)
+                .map(|d| (d.version, d.flags))
+                .collect()
+        };
+        assert_eq!(versions(&dst), versions(&src), "{:?}", id.name());
+    }
+}
+
+#[test]
+fn load_device_state_rejects_mismatched_payloads() {
+    let mut m = fixture_machine();
+    let e1000 = m.save_device_state(DeviceId::E1000).unwrap();
+
+    // Wrong outer id.
+    assert!(m.load_device_state(DeviceId::PIT, &e1000).is_err());
+    // Truncated framing.
+    assert!(m
+        .load_device_state(DeviceId::E1000, &e1000[..e1000.len() - 1])
+        .is_err());
+
+    // Another device's io-snapshot payload under the E1000 id.
+    let rng = m.save_device_state(DeviceId::VIRTIO_RNG).unwrap();
+    let mut forged = rng.clone();
+    forged[0..4].copy_from_slice(&DeviceId::E1000.0.to_le_bytes());
+    assert!(m.load_device_state(DeviceId::E1000, &forged).is_err());
+
+    // Unsupported major version (outer version and io-snapshot header agree).
+    let mut future = e1000.clone();
+    let major = u16::from_le_bytes([future[4], future[5]]) + 1;
+    future[4..6].copy_from_slice(&major.to_le_bytes());
+    future[16 + 12..16 + 14].copy_from_slice(&major.to_le_bytes());
+    assert!(m.load_device_state(DeviceId::E1000, &future).is_err());
+
+    // The original still loads.
+    m.load_device_state(DeviceId::E1000, &e1000).unwrap();
+}
+
+#[test]
+fn golden_device_state_fixtures_load_into_current_build() {
+    let current = fixture_machine();
+    let manifest = current.device_snapshot_manifest();
+
+    if env::var_os(UPDATE_ENV).is_some() {
+        fs::create_dir_all(fixture_dir()).unwrap();
+        for entry in &manifest {
+            let path = fixture_dir().join(fixture_name(entry.id, entry.version, entry.flags));
+            if !path.exists() {
+                fs::write(&path, current.save_device_state(entry.id).unwrap()).unwrap();
+                eprintln!("recorded {}", path.display());
+            }
+        }
+    }
+
+    // (id, version) -> flags of every fixture, for the coverage check below.
+    let mut recorded: BTreeMap<(u32, u16), BTreeSet<u16>> = BTreeMap::new();
+    for (path, bytes) in read_fixtures() {
+        let (id, version, flags) = blob_header(&bytes);
+        recorded.entry((id.0, version)).or_default().insert(flags);
+
+        let mut m = fixture_machine();
+        m.load_device_state(id, &bytes)
+            .unwrap_or_else(|e| panic!("{} no longer loads: {e}", path.display()));
+
+        // The device keeps its major version and never reports an older minor than it loaded.
+        let live: Vec<_> = m
+            .device_snapshot_manifest()
+            .into_iter()
+            .filter(|d| d.id == id)
+            .collect();
+        assert!(
+            live.iter()
+                .any(|d| d.version == version && d.flags >= flags),
+            "{}: loaded into {live:?}",
+            path.display()
+        );
+
+        // The loaded state can itself be saved and loaded again.
+        let again = m.save_device_state(id).unwrap();
+        m.load_device_state(id, &again)
+            .unwrap_or_else(|e| panic!("{}: re-saved state does not load: {e}", path.display()));
+    }
+
+    // A device that is part of the corpus must get a fixture for each new format it ships.
+    let corpus_ids: BTreeSet<u32> = recorded.keys().map(|(id, _)| *id).collect();
+    let missing: Vec<String> = manifest
+        .iter()
+        .filter(|d| corpus_ids.contains(&d.id.0))
+        .filter(|d| {
+            !recorded
+                .get(&(d.id.0, d.version))
+                .is_some_and(|flags| flags.contains(&d.flags))
+        })
+        .map(|d| fixture_name(d.id, d.version, d.flags))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "device state formats without a fixture: {missing:?}\nRecord them with {UPDATE_ENV}=1"
+    );
+}
@@ -0,0 +1,142 @@
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_io_snapshot::io::state::IoSnapshot;
+use aero_machine::{Machine, MachineConfig};
+use aero_platform::interrupts::{InterruptController, PlatformInterruptMode};
+use pretty_assertions::assert_eq;
+
+const HPET_VECTOR: u8 = 0x61;
+const STEP_NS: u64 = 100_000;
+
+fn new_machine(scheduling: bool) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_uhci: true,
+        enable_ehci: true,
+        enable_e1000: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_device_tick_scheduling(scheduling);
+    m
+}
+
+#[test]
+fn idle_devices_are_skipped_and_scheduling_survives_reset() {
+    let mut m = new_machine(true);
+    assert!(m.device_tick_scheduling());
+    m.reset_perf_counters();
+
+    for _ in 0..100 {
+        m.tick_platform(1_000_000);
+        m.poll_network();
+    }
+    let perf = m.perf_counters();
+    // Every device runs once after scheduling is (re)enabled, then stays idle.
+    for (executed, skipped) in [
+        (perf.device_ticks.uhci, perf.skipped_device_ticks.uhci),
+        (perf.device_ticks.ehci, perf.skipped_device_ticks.ehci),
+        (perf.device_ticks.hpet, perf.skipped_device_ticks.hpet),
+        (perf.device_ticks.network, perf.skipped_device_ticks.network),
+    ] {
+        assert!(executed <= 1, "{perf:?}");
+        assert_eq!(executed + skipped, 100, "{perf:?}");
+    }
+    // Always-on timers are not scheduled.
+    assert_eq!(perf.device_ticks.pit, 100);
+
+    m.reset();
+    assert!(m.device_tick_scheduling());
+
+    m.set_device_tick_scheduling(false);
+    m.reset_perf_counters();
+    for _ in 0..10 {
+        m.tick_platform(1_000_000);
+        m.poll_network();
+    }
+    let perf = m.perf_counters();
+    assert_eq!(perf.device_ticks.uhci, 10);
+    assert_eq!(perf.device_ticks.ehci, 10);
+    assert_eq!(perf.device_ticks.network, 10);
+    assert_eq!(perf.skipped_device_ticks, Default::default());
+}
+
+/// Route HPET timer 0 to GSI2 (edge, vector [`HPET_VECTOR`]) and arm it periodic at 1ms.
+fn arm_hpet(m: &mut Machine) {
+    {
+        let interrupts = m.platform_interrupts().unwrap();
+        let mut ints = interrupts.borrow_mut();
+        ints.set_mode(PlatformInterruptMode::Apic);
+        ints.ioapic_mmio_write(0x00, 0x10 + 2 * 2);
+        ints.ioapic_mmio_write(0x10, u32::from(HPET_VECTOR));
+        ints.ioapic_mmio_write(0x00, 0x10 + 2 * 2 + 1);
+        ints.ioapic_mmio_write(0x10, 0);
+    }
+
+    // Fast A20 gate: the HPET base aliases the IOAPIC while A20 is masked.
+    m.io_write(0x92, 1, 0x02);
+    // Timer0: route 2, edge-triggered, interrupt enabled, periodic, 1ms (10_000 tick) period.
+    m.write_physical_u64(
+        HPET_MMIO_BASE + 0x100,
+        (2 << 9) | (1 << 2) | (1 << 3) | (1 << 6),
+    );
+    m.write_physical_u64(HPET_MMIO_BASE + 0x108, 10_000);
+    m.write_physical_u64(HPET_MMIO_BASE + 0x010, 1);
+}
+
+/// Steps at which the HPET vector was delivered, acknowledging each delivery. Halfway through,
+/// timer 0 is reprogrammed to a one-shot 0.25ms out.
+fn hpet_deliveries(m: &mut Machine) -> Vec<usize> {
+    let interrupts = m.platform_interrupts().unwrap();
+    let mut delivered = Vec::new();
+    for step in 0..60 {
+        if step == 30 {
+            let counter = m.read_physical_u64(HPET_MMIO_BASE + 0x0F0);
+            m.write_physical_u64(HPET_MMIO_BASE + 0x100, (2 << 9) | (1 << 2));
+            m.write_physical_u64(HPET_MMIO_BASE + 0x108, counter + 2_500);
+        }
+        m.tick_platform(STEP_NS);
+        let pending = interrupts.borrow().get_pending();
+        if let Some(vector) = pending {
+            assert_eq!(vector, HPET_VECTOR);
+            let mut ints = interrupts.borrow_mut();
+            ints.acknowledge(vector);
+            ints.eoi(vector);
+            drop(ints);
+            // Edge-triggered timers only pulse again once the status bit is cleared.
+            m.write_physical_u64(HPET_MMIO_BASE + 0x020, 1);
+            delivered.push(step);
+        }
+    }
+    delivered
+}
+
+#[test]
+fn hpet_interrupt_timing_does_not_depend_on_scheduling() {
+    let mut scheduled = new_machine(true);
+    let mut every_step = new_machine(false);
+
+    arm_hpet(&mut scheduled);
+    arm_hpet(&mut every_step);
+    scheduled.reset_perf_counters();
+
+    let expected = hpet_deliveries(&mut every_step);
+    assert_eq!(expected, vec![9, 19, 29, 32]);
+    assert_eq!(hpet_deliveries(&mut scheduled), expected);
+    assert!(
+        scheduled.perf_counters().skipped_device_ticks.hpet >= 40,
+        "{:?}",
+        scheduled.perf_counters()
+    );
+
+    // The lazily advanced main counter is caught up before state is saved.
+    scheduled.tick_platform(STEP_NS / 2);
+    every_step.tick_platform(STEP_NS / 2);
+    scheduled.take_snapshot_full().unwrap();
+    every_step.take_snapshot_full().unwrap();
+    let hpet_state = |m: &Machine| m.hpet().unwrap().borrow().save_state();
+    assert_eq!(hpet_state(&scheduled), hpet_state(&every_step));
+}
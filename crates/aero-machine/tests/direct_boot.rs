@@ -0,0 +1,150 @@
+use aero_cpu_core::state::{gpr, CpuMode};
+use aero_machine::{
+    DirectBootError, DirectBootMode, DirectBootPaging, DirectBootSpec, Machine, MachineConfig,
+    RunExit,
+};
+use pretty_assertions::assert_eq;
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+#[test]
+fn direct_boot_enters_flat_protected_mode() {
+    let mut m = new_machine();
+    let mut regs = [0u64; 16];
+    regs[gpr::RAX] = 1;
+    regs[gpr::RBX] = 2;
+    // mov dword [0x2000], 0xDEADBEEF; add eax, ebx; hlt
+    let code = vec![
+        0xC7, 0x05, 0x00, 0x20, 0x00, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x01, 0xD8, 0xF4,
+    ];
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Protected32,
+        entry: 0x10_0000,
+        gpr: regs,
+        blobs: vec![(0x10_0000, code)],
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(m.cpu().mode, CpuMode::Protected);
+
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u32(0x2000), 0xDEAD_BEEF);
+    assert_eq!(m.cpu().gpr[gpr::RAX], 3);
+}
+
+#[test]
+fn direct_boot_enters_long_mode_with_identity_map() {
+    let mut m = new_machine();
+    let mut regs = [0u64; 16];
+    regs[gpr::RSP] = 0x8000;
+    // mov rcx, 0x1122334455667788; mov [0x3000], rcx; push rcx; pop rdx; hlt
+    let code = vec![
+        0x48, 0xB9, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x48, 0x89, 0x0C, 0x25, 0x00,
+        0x30, 0x00, 0x00, 0x51, 0x5A, 0xF4,
+    ];
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Long64,
+        entry: 0x20_0000,
+        gpr: regs,
+        paging: DirectBootPaging::IdentityMap {
+            gib: 1,
+            tables_paddr: 0x1_0000,
+        },
+        blobs: vec![(0x20_0000, code)],
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(m.cpu().mode, CpuMode::Long);
+
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u64(0x3000), 0x1122_3344_5566_7788);
+    assert_eq!(m.cpu().gpr[gpr::RDX], 0x1122_3344_5566_7788);
+    assert_eq!(m.cpu().gpr[gpr::RSP], 0x8000);
+}
+
+#[test]
+fn direct_boot_enters_real_mode_without_firmware() {
+    let mut m = new_machine();
+    // mov ax, 0x1234; hlt
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Real,
+        entry: 0x1_0010,
+        blobs: vec![(0x1_0010, vec![0xB8, 0x34, 0x12, 0xF4])],
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(m.cpu().mode, CpuMode::Real);
+    assert_eq!(m.cpu().segments.cs.selector, 0x1001);
+
+    run_until_halt(&mut m);
+    assert_eq!(m.cpu().gpr[gpr::RAX] & 0xFFFF, 0x1234);
+}
+
+#[test]
+fn direct_boot_validates_spec() {
+    let mut m = new_machine();
+    assert_eq!(
+        m.boot_direct(DirectBootSpec {
+            mode: DirectBootMode::Long64,
+            ..Default::default()
+        }),
+        Err(DirectBootError::LongModeRequiresPaging)
+    );
+    assert_eq!(
+        m.boot_direct(DirectBootSpec {
+            entry: 0x4000_0000,
+            ..Default::default()
+        }),
+        Err(DirectBootError::EntryOutsideRam(0x4000_0000))
+    );
+    assert_eq!(
+        m.boot_direct(DirectBootSpec {
+            mode: DirectBootMode::Real,
+            entry: 0x10_0000,
+            ..Default::default()
+        }),
+        Err(DirectBootError::EntryOutsideRam(0x10_0000))
+    );
+    assert_eq!(
+        m.boot_direct(DirectBootSpec {
+            blobs: vec![(0xFF_FFF0, vec![0; 0x20])],
+            ..Default::default()
+        }),
+        Err(DirectBootError::BlobOutsideRam {
+            paddr: 0xFF_FFF0,
+            len: 0x20
+        })
+    );
+    assert_eq!(
+        m.boot_direct(DirectBootSpec {
+            paging: DirectBootPaging::IdentityMap {
+                gib: 5,
+                tables_paddr: 0x1_0000,
+            },
+            ..Default::default()
+        }),
+        Err(DirectBootError::InvalidIdentityMapSize(5))
+    );
+}
@@ -0,0 +1,152 @@
+use aero_machine::{Machine, MachineConfig, RunExit, DIRTY_SAMPLING_HISTORY};
+use pretty_assertions::assert_eq;
+
+// cli; mov ax, 0x1000; mov es, ax; xor di, di
+// loop: inc byte [es:di]; add di, 0x1000; and di, 0x3000; jmp loop
+//
+// Touches the four pages at 0x10000..0x14000 round-robin and nothing else.
+const FOUR_PAGE_LOOP: &[u8] = &[
+    0xFA, 0xB8, 0x00, 0x10, 0x8E, 0xC0, 0x31, 0xFF, 0x26, 0xFE, 0x05, 0x81, 0xC7, 0x00, 0x10, 0x81,
+    0xE7, 0x00, 0x30, 0xEB, 0xF3,
+];
+const LOOP_PAGES_BASE: u64 = 0x10000;
+const LOOP_PAGES: u64 = 4;
+/// Pages dirtied every interval: the loop's pages plus page 0, where the BIOS refreshes the BDA
+/// tick count on every platform tick.
+const SAMPLED_PAGES: u64 = LOOP_PAGES + 1;
+const PAGE_SIZE: u64 = 4096;
+
+// Interval shorter than one `SLICE_INSTS` slice, so every slice boundary closes an interval.
+const INTERVAL_NS: u64 = 10_000;
+const SLICE_INSTS: u64 = 100_000;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(FOUR_PAGE_LOOP)).unwrap();
+    m.reset();
+    m
+}
+
+fn run_slices(m: &mut Machine, slices: usize) {
+    for _ in 0..slices {
+        let exit = m.run_slice(SLICE_INSTS);
+        assert!(matches!(exit, RunExit::Completed { .. }), "{exit:?}");
+    }
+}
+
+#[test]
+fn sampling_reports_rate_and_working_set() {
+    let mut m = new_machine();
+    assert_eq!(m.dirty_rate_stats(), None);
+
+    m.start_dirty_sampling(INTERVAL_NS);
+    let stats = m.dirty_rate_stats().unwrap();
+    assert_eq!(stats.interval_ns, INTERVAL_NS);
+    assert_eq!(stats.intervals, 0);
+    assert_eq!(stats.working_set_pages, 0);
+
+    // The first boundary only takes the baseline.
+    run_slices(&mut m, 4);
+    assert_eq!(m.dirty_rate_stats().unwrap().intervals, 3);
+
+    run_slices(&mut m, DIRTY_SAMPLING_HISTORY * 2);
+    let stats = m.dirty_rate_stats().unwrap();
+    assert_eq!(stats.intervals, DIRTY_SAMPLING_HISTORY);
+    assert_eq!(stats.last_interval_pages, SAMPLED_PAGES);
+    assert_eq!(stats.working_set_pages, SAMPLED_PAGES);
+    assert_eq!(stats.working_set_bytes, SAMPLED_PAGES * PAGE_SIZE);
+    // Each interval dirties the same pages and lasts at least `INTERVAL_NS`.
+    assert!(stats.pages_per_sec > 0, "{stats:?}");
+    assert!(
+        stats.pages_per_sec <= SAMPLED_PAGES * 1_000_000_000 / INTERVAL_NS,
+        "{stats:?}"
+    );
+
+    m.stop_dirty_sampling();
+    assert_eq!(m.dirty_rate_stats(), None);
+}
+
+#[test]
+fn sampling_restarts_after_reset() {
+    let mut m = new_machine();
+    m.start_dirty_sampling(INTERVAL_NS);
+    run_slices(&mut m, 4);
+    assert_eq!(m.dirty_rate_stats().unwrap().intervals, 3);
+
+    m.reset();
+    let stats = m.dirty_rate_stats().unwrap();
+    assert_eq!(stats.intervals, 0);
+    assert_eq!(stats.working_set_pages, 0);
+
+    // Firmware POST writes from the reset are discarded with the new baseline.
+    run_slices(&mut m, 2);
+    let stats = m.dirty_rate_stats().unwrap();
+    assert_eq!(stats.intervals, 1);
+    assert_eq!(stats.working_set_pages, SAMPLED_PAGES);
+}
+
+#[test]
+fn sampling_does_not_corrupt_dirty_snapshots() {
+    let mut m = new_machine();
+    m.start_dirty_sampling(INTERVAL_NS);
+    run_slices(&mut m, 2);
+    let base = m.take_snapshot_full().unwrap();
+
+    // A page dirtied once, right after the base snapshot, is harvested by many sampling intervals
+    // before the diff is taken; it must still be part of the diff.
+    let pattern: Vec<u8> = (0..PAGE_SIZE as usize)
+        .map(|i| (i as u8).wrapping_mul(13))
+        .collect();
+    m.write_physical(0x20000, &pattern);
+    run_slices(&mut m, DIRTY_SAMPLING_HISTORY);
+    let stats = m.dirty_rate_stats().unwrap();
+    assert_eq!(stats.working_set_pages, SAMPLED_PAGES + 1, "{stats:?}");
+
+    let diff = m.take_snapshot_dirty().unwrap();
+    // Taking the diff does not drain the sampling bitmap either.
+    run_slices(&mut m, 1);
+    assert_eq!(
+        m.dirty_rate_stats().unwrap().last_interval_pages,
+        SAMPLED_PAGES
+    );
+
+    let loop_pages = (LOOP_PAGES * PAGE_SIZE) as usize;
+    let expected_loop = {
+        let mut restored = new_machine();
+        restored.restore_snapshot_bytes(&base).unwrap();
+        restored.restore_snapshot_bytes(&diff).unwrap();
+        assert_eq!(
+            restored.read_physical_bytes(0x20000, pattern.len()),
+            pattern
+        );
+        restored.read_physical_bytes(LOOP_PAGES_BASE, loop_pages)
+    };
+
+    // The diff carries the loop counters as of the diff; compare against a sampling-free run.
+    let mut reference = new_machine();
+    run_slices(&mut reference, 2);
+    reference.write_physical(0x20000, &pattern);
+    run_slices(&mut reference, DIRTY_SAMPLING_HISTORY);
+    assert_eq!(
+        reference.read_physical_bytes(LOOP_PAGES_BASE, loop_pages),
+        expected_loop
+    );
+}
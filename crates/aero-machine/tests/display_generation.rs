@@ -0,0 +1,161 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_protocol::aerogpu::aerogpu_pci as pci;
+use pretty_assertions::assert_eq;
+
+fn build_idle_loop_boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    // sti; jmp $
+    sector[..3].copy_from_slice(&[0xFB, 0xEB, 0xFE]);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn boot(cfg: MachineConfig) -> Machine {
+    let mut m = Machine::new(cfg).unwrap();
+    m.set_disk_image(build_idle_loop_boot_sector().to_vec())
+        .unwrap();
+    m.reset();
+    run_idle(&mut m);
+    m
+}
+
+fn run_idle(m: &mut Machine) {
+    for _ in 0..10 {
+        match m.run_slice(10_000) {
+            RunExit::Completed { .. } | RunExit::Halted { .. } => {}
+            other => panic!("unexpected run exit: {other:?}"),
+        }
+    }
+}
+
+fn aerogpu_machine() -> Machine {
+    boot(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        // Keep the machine minimal and deterministic.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+}
+
+/// Asserts that `f` bumps the display generation and that the next present re-renders once.
+#[track_caller]
+fn assert_bumps(m: &mut Machine, what: &str, f: impl FnOnce(&mut Machine)) {
+    let before = m.display_generation();
+    f(m);
+    assert!(m.display_generation() > before, "{what} should bump");
+    assert!(m.display_present(false), "{what} should re-render");
+    assert!(!m.display_present(false), "{what}: second present");
+}
+
+#[test]
+fn aerogpu_display_generation_tracks_guest_display_writes() {
+    let mut m = aerogpu_machine();
+    let bar0 = m.aerogpu_bar0_base().expect("BAR0 assigned by POST");
+    let bar1 = m.aerogpu_vram_bar_base().expect("BAR1 assigned by POST");
+
+    assert!(m.display_present(false), "first present renders");
+    let generation = m.display_generation();
+    assert!(!m.display_present(false));
+
+    // Idle guest time, guest reads and the present itself leave the generation alone.
+    run_idle(&mut m);
+    m.read_physical_u8(0xB8000);
+    m.read_physical_u32(bar1);
+    m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH));
+    m.io_read(0x3C9, 1);
+    m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_IRQ_ENABLE), 0);
+    assert_eq!(m.display_generation(), generation);
+    assert!(!m.display_present(false));
+    assert_eq!(m.display_generation(), generation);
+
+    // `force` re-renders without touching the counter.
+    assert!(m.display_present(true));
+    assert_eq!(m.display_generation(), generation);
+
+    assert_bumps(&mut m, "text write", |m| {
+        m.write_physical_u16(0xB8000, 0x1F41);
+    });
+    let (w, h) = m.display_resolution();
+    assert_eq!((w, h), (720, 400));
+
+    assert_bumps(&mut m, "BAR1 VRAM write", |m| m.write_physical_u32(bar1, 1));
+    assert_bumps(&mut m, "scanout register write", |m| {
+        m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH), 640);
+    });
+    assert_bumps(&mut m, "cursor move", |m| {
+        m.write_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_CURSOR_X), 10);
+    });
+    assert_bumps(&mut m, "DAC write", |m| {
+        m.io_write(0x3C8, 1, 1);
+        m.io_write(0x3C9, 1, 0x3F);
+    });
+
+    // Reset clears the cached frame but keeps the counter monotonic.
+    let before_reset = m.display_generation();
+    m.reset();
+    assert!(m.display_generation() > before_reset);
+    assert!(m.display_present(false));
+}
+
+#[test]
+fn display_generation_survives_snapshot_restore() {
+    let mut m = aerogpu_machine();
+    m.write_physical_u16(0xB8000, 0x1F41);
+    m.display_present(false);
+    let generation = m.display_generation();
+    let snap = m.take_snapshot_full().unwrap();
+
+    let mut restored = aerogpu_machine();
+    restored.display_present(false);
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    assert_eq!(restored.display_generation(), generation);
+    assert!(
+        restored.display_present(false),
+        "first present after restore must render"
+    );
+    assert_eq!(restored.display_framebuffer(), m.display_framebuffer());
+    assert!(!restored.display_present(false));
+}
+
+#[test]
+fn vga_display_generation_tracks_guest_display_writes() {
+    let mut m = boot(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_vga: true,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    });
+
+    assert!(m.display_present(false));
+    let generation = m.display_generation();
+    run_idle(&mut m);
+    m.read_physical_u8(0xB8000);
+    assert_eq!(m.display_generation(), generation);
+    assert!(!m.display_present(false));
+
+    assert_bumps(&mut m, "text write", |m| {
+        m.write_physical_u16(0xB8000, 0x1F41);
+    });
+    assert_bumps(&mut m, "CRTC cursor move", |m| {
+        m.io_write(0x3D4, 1, 0x0F);
+        m.io_write(0x3D5, 1, 0x05);
+    });
+    assert_bumps(&mut m, "DAC write", |m| {
+        m.io_write(0x3C8, 1, 1);
+        m.io_write(0x3C9, 1, 0x3F);
+    });
+    let lfb = m.vbe_lfb_base();
+    assert_bumps(&mut m, "LFB write", |m| {
+        m.write_physical_u32(lfb, 0x00FF_0000)
+    });
+}
@@ -0,0 +1,146 @@
+use aero_devices::clock::Clock as _;
+use aero_machine::{DisplayRecordingConfig, Machine, MachineConfig};
+use pretty_assertions::assert_eq;
+
+const MS: u64 = 1_000_000;
+
+fn base_cfg() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        // Keep the test deterministic/minimal.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    }
+}
+
+fn set_vbe_mode(m: &mut Machine, xres: u16, yres: u16) {
+    for (index, value) in [
+        (0x0004, 0x0000), // disable while reprogramming
+        (0x0001, xres),
+        (0x0002, yres),
+        (0x0003, 32),
+        (0x0006, xres),
+        (0x0007, yres),
+        (0x0004, 0x0041), // enable + lfb
+    ] {
+        m.io_write(0x01CE, 2, index);
+        m.io_write(0x01CF, 2, u32::from(value));
+    }
+}
+
+fn now_ns(m: &Machine) -> u64 {
+    m.platform_clock().expect("PC platform clock").now_ns()
+}
+
+fn fill(m: &mut Machine, pixels: &[u32]) {
+    let base = m.vbe_lfb_base();
+    for (i, &px) in pixels.iter().enumerate() {
+        m.write_physical_u32(base + i as u64 * 4, px);
+    }
+}
+
+#[test]
+fn display_recording_follows_mode_change_and_skips_identical_frames() {
+    let mut m = Machine::new(base_cfg()).unwrap();
+    m.reset();
+    m.start_display_recording(DisplayRecordingConfig {
+        max_frames: 16,
+        min_frame_interval_guest_ns: MS,
+        scale: None,
+    });
+
+    set_vbe_mode(&mut m, 4, 4);
+    fill(&mut m, &[0x0000_00FF; 16]);
+    let t0 = now_ns(&m);
+    m.display_present(false);
+    let first = m.display_framebuffer().to_vec();
+
+    // Re-presenting the same content (forced, and after time passes) must not record it again.
+    m.tick_platform(2 * MS);
+    m.display_present(true);
+    m.display_present(false);
+
+    // A change within the frame interval is held back until the interval elapses.
+    m.tick_platform(2 * MS);
+    let t1 = now_ns(&m);
+    fill(&mut m, &[0x0000_FF00; 16]);
+    m.display_present(false);
+    m.tick_platform(MS / 2);
+    fill(&mut m, &[0x00FF_0000; 16]);
+    m.display_present(false);
+    let third = m.display_framebuffer().to_vec();
+    m.tick_platform(MS);
+    let t2 = now_ns(&m);
+    m.display_present(false);
+
+    // Mode change: 4x4 -> 8x2.
+    m.tick_platform(5 * MS);
+    set_vbe_mode(&mut m, 8, 2);
+    let t3 = now_ns(&m);
+    m.display_present(false);
+    assert_eq!(m.display_resolution(), (8, 2));
+    let fourth = m.display_framebuffer().to_vec();
+
+    let recorded = m.take_recorded_frames();
+    assert_eq!(recorded.dropped_frames, 0);
+    let summary: Vec<_> = recorded
+        .frames
+        .iter()
+        .map(|f| (f.guest_time_ns, f.width, f.height))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![(t0, 4, 4), (t1, 4, 4), (t2, 4, 4), (t3, 8, 2)]
+    );
+    assert_eq!(recorded.frames[0].pixels, first);
+    assert_ne!(recorded.frames[1].pixels, first);
+    assert_eq!(recorded.frames[2].pixels, third);
+    assert_eq!(recorded.frames[3].pixels, fourth);
+
+    // The queue was drained.
+    assert_eq!(m.take_recorded_frames().frames.len(), 0);
+}
+
+#[test]
+fn display_recording_evicts_oldest_frames_and_downscales() {
+    let mut m = Machine::new(base_cfg()).unwrap();
+    m.reset();
+    m.start_display_recording(DisplayRecordingConfig {
+        max_frames: 2,
+        min_frame_interval_guest_ns: 0,
+        scale: Some((2, 1)),
+    });
+
+    set_vbe_mode(&mut m, 4, 2);
+    let mut times = Vec::new();
+    for shade in [0x10u32, 0x20, 0x30] {
+        m.tick_platform(MS);
+        // Left half `shade`, right half `shade * 2` (blue channel of BGRX).
+        let row = [shade, shade, shade * 2, shade * 2];
+        fill(&mut m, &[row, row].concat());
+        times.push(now_ns(&m));
+        m.display_present(false);
+    }
+
+    let full = m.display_framebuffer().to_vec();
+    let recorded = m.take_recorded_frames();
+    assert_eq!(recorded.dropped_frames, 1);
+    let stamps: Vec<_> = recorded.frames.iter().map(|f| f.guest_time_ns).collect();
+    assert_eq!(stamps, times[1..].to_vec());
+
+    // Each output pixel averages a uniform 2x2 source box.
+    let last = &recorded.frames[1];
+    assert_eq!((last.width, last.height), (2, 1));
+    assert_eq!(last.pixels, vec![full[0], full[3]]);
+
+    // The drop counter resets once reported.
+    assert_eq!(m.take_recorded_frames().dropped_frames, 0);
+}
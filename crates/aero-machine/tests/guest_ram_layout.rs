@@ -0,0 +1,154 @@
+use aero_devices::a20_gate::A20_GATE_PORT;
+use aero_devices::pci::profile;
+use aero_machine::{Machine, MachineConfig, RamRegionDescriptor};
+use aero_protocol::aerogpu::aerogpu_pci as pci;
+use aero_snapshot as snapshot;
+use firmware::bios::PCIE_ECAM_BASE;
+use pretty_assertions::assert_eq;
+use std::sync::Mutex;
+
+const FOUR_GIB: u64 = 0x1_0000_0000;
+const HIGH_RAM_LEN: u64 = 0x10_0000;
+
+// The remap tests configure >2.75GiB of (sparse) guest RAM; run them one at a time.
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+fn remapped_machine(enable_aerogpu: bool) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: PCIE_ECAM_BASE + HIGH_RAM_LEN,
+        enable_pc_platform: enable_aerogpu,
+        enable_vga: false,
+        enable_aerogpu,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn small_ram_is_a_single_identity_region() {
+    let ram_size_bytes = 16 * 1024 * 1024;
+    let m = Machine::new(MachineConfig {
+        ram_size_bytes,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(
+        m.guest_ram_layout(),
+        vec![RamRegionDescriptor {
+            gpa_start: 0,
+            len: ram_size_bytes,
+            backing_offset: 0,
+        }]
+    );
+    assert_eq!(m.gpa_to_backing_offset(0x1234), Some(0x1234));
+    assert_eq!(
+        m.gpa_to_backing_offset(ram_size_bytes - 1),
+        Some(ram_size_bytes - 1)
+    );
+    assert_eq!(m.gpa_to_backing_offset(ram_size_bytes), None);
+}
+
+#[test]
+fn remapped_ram_translates_around_the_pci_hole() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = remapped_machine(false);
+
+    assert_eq!(
+        m.guest_ram_layout(),
+        vec![
+            RamRegionDescriptor {
+                gpa_start: 0,
+                len: PCIE_ECAM_BASE,
+                backing_offset: 0,
+            },
+            RamRegionDescriptor {
+                gpa_start: FOUR_GIB,
+                len: HIGH_RAM_LEN,
+                backing_offset: PCIE_ECAM_BASE,
+            },
+        ]
+    );
+
+    // Below the hole.
+    assert_eq!(m.gpa_to_backing_offset(0x10_0000), Some(0x10_0000));
+    assert_eq!(
+        m.gpa_to_backing_offset(PCIE_ECAM_BASE - 1),
+        Some(PCIE_ECAM_BASE - 1)
+    );
+    // In the hole.
+    assert_eq!(m.gpa_to_backing_offset(PCIE_ECAM_BASE), None);
+    assert_eq!(m.gpa_to_backing_offset(0xFEE0_0000), None);
+    assert_eq!(m.gpa_to_backing_offset(FOUR_GIB - 1), None);
+    // The >4GiB remap region, and past its end.
+    assert_eq!(m.gpa_to_backing_offset(FOUR_GIB), Some(PCIE_ECAM_BASE));
+    assert_eq!(
+        m.gpa_to_backing_offset(FOUR_GIB + 0x1234),
+        Some(PCIE_ECAM_BASE + 0x1234)
+    );
+    assert_eq!(m.gpa_to_backing_offset(FOUR_GIB + HIGH_RAM_LEN), None);
+
+    // The translated offset addresses the same byte in the dense RAM image.
+    let pattern = [0xDE, 0xAD, 0xBE, 0xEF];
+    m.write_physical(FOUR_GIB + 0x1234, &pattern);
+    let offset = m.gpa_to_backing_offset(FOUR_GIB + 0x1234).unwrap();
+    let mut buf = [0u8; 4];
+    snapshot::SnapshotSource::read_ram(&m, offset, &mut buf).unwrap();
+    assert_eq!(buf, pattern);
+}
+
+#[test]
+fn scanout_from_high_ram_reads_the_translated_backing_offset() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = remapped_machine(true);
+    m.io_write(A20_GATE_PORT, 1, 0x02);
+
+    let bar0_base = {
+        let pci_cfg = m.pci_config_ports().expect("pc platform enabled");
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        let cfg = pci_cfg
+            .bus_mut()
+            .device_config_mut(profile::AEROGPU.bdf)
+            .expect("AeroGPU must exist when enable_aerogpu=true");
+        cfg.set_command(0x0006); // MEM + BME
+        cfg.bar_range(profile::AEROGPU_BAR0_INDEX)
+            .expect("missing AeroGPU BAR0")
+            .base
+    };
+
+    // Fill the framebuffer the way a host sharing the backing would: by backing offset.
+    let fb_gpa = FOUR_GIB + 0x4000;
+    let (width, height) = (2u32, 2u32);
+    let rgba = [0x12u8, 0x34, 0x56, 0x78];
+    let mut fb = vec![0u8; (width * height * 4) as usize];
+    fb[12..16].copy_from_slice(&[rgba[2], rgba[1], rgba[0], rgba[3]]);
+    let offset = m.gpa_to_backing_offset(fb_gpa).unwrap();
+    snapshot::SnapshotTarget::write_ram(&mut m, offset, &fb).unwrap();
+
+    for (reg, value) in [
+        (pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH, width),
+        (pci::AEROGPU_MMIO_REG_SCANOUT0_HEIGHT, height),
+        (
+            pci::AEROGPU_MMIO_REG_SCANOUT0_FORMAT,
+            pci::AerogpuFormat::B8G8R8A8Unorm as u32,
+        ),
+        (pci::AEROGPU_MMIO_REG_SCANOUT0_PITCH_BYTES, width * 4),
+        (pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_LO, fb_gpa as u32),
+        (
+            pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_HI,
+            (fb_gpa >> 32) as u32,
+        ),
+        (pci::AEROGPU_MMIO_REG_SCANOUT0_ENABLE, 1),
+    ] {
+        m.write_physical_u32(bar0_base + u64::from(reg), value);
+    }
+
+    m.display_present(true);
+    assert_eq!(m.display_resolution(), (width, height));
+    assert_eq!(m.display_framebuffer()[3], u32::from_le_bytes(rgba));
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+#[test]
+fn hang_watchdog_reports_cli_jmp_self_loop() {
+    // cli; jmp $
+    let mut m = new_machine(&[0xFA, 0xEB, 0xFE]);
+    m.set_hang_watchdog(Some(Duration::from_micros(10)));
+
+    let details = (0..100)
+        .find_map(|_| match m.run_slice(10_000) {
+            RunExit::HangSuspected { details, executed } => {
+                assert_eq!(executed, 10_000);
+                Some(details)
+            }
+            RunExit::Completed { .. } => None,
+            other => panic!("unexpected exit: {other:?}"),
+        })
+        .expect("watchdog did not fire for a `cli; jmp $` guest");
+
+    assert!(details.stalled_ns >= 10_000, "{details:?}");
+    assert_eq!(details.vcpus.len(), 1);
+    let bsp = details.vcpus[0];
+    assert_eq!(bsp.rip & 0xFFFF, 0x7C01);
+    assert_eq!(bsp.cpl, 0);
+    assert!(!bsp.interrupts_enabled);
+    assert!(!bsp.halted);
+
+    // The guest is still hung, so the watchdog keeps reporting it once per threshold.
+    assert!((0..100).any(|_| matches!(m.run_slice(10_000), RunExit::HangSuspected { .. })));
+
+    // Disarming restores plain slice completion.
+    m.set_hang_watchdog(None);
+    for _ in 0..3 {
+        assert!(matches!(m.run_slice(10_000), RunExit::Completed { .. }));
+    }
+}
+
+#[test]
+fn hang_watchdog_ignores_hlt_idle_loop() {
+    // sti; hlt; jmp short -3 (back to hlt)
+    let mut m = new_machine(&[0xFB, 0xF4, 0xEB, 0xFD]);
+    m.set_hang_watchdog(Some(Duration::from_micros(100)));
+
+    let mut halted = 0;
+    for _ in 0..200 {
+        match m.run_slice(50_000) {
+            RunExit::Halted { .. } => halted += 1,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    assert!(halted > 0);
+}
@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_devices::dma::{DmaChannelClient, DmaClientError, DmaCycle};
+use aero_machine::{Machine, MachineConfig, MachineError};
+use pretty_assertions::assert_eq;
+
+#[derive(Default)]
+struct PatternSource {
+    remaining: usize,
+    terminal_count: bool,
+}
+
+impl DmaChannelClient for PatternSource {
+    fn dma_request(&self) -> bool {
+        self.remaining != 0
+    }
+
+    fn dma_ack(&mut self, cycle: DmaCycle<'_>) {
+        if let DmaCycle::ToMemory(data) = cycle {
+            data.fill(0xA0 | self.remaining as u8);
+        }
+        self.remaining -= 1;
+    }
+
+    fn dma_terminal_count(&mut self) {
+        self.terminal_count = true;
+    }
+}
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn registered_client_transfers_into_guest_memory() {
+    let mut m = new_machine();
+    let client = Rc::new(RefCell::new(PatternSource {
+        remaining: 4,
+        ..Default::default()
+    }));
+    m.register_isa_dma_client(1, client.clone()).unwrap();
+    assert_eq!(
+        m.register_isa_dma_client(1, client.clone()),
+        Err(MachineError::IsaDmaClient(DmaClientError::ChannelInUse(1)))
+    );
+    // Registrations survive reset.
+    m.reset();
+
+    // Channel 1: single mode, write-to-memory, 4 bytes at 0x2_3000.
+    m.io_write(0x0A, 1, 0x05);
+    m.io_write(0x0C, 1, 0);
+    m.io_write(0x0B, 1, 0x45);
+    m.io_write(0x02, 1, 0x00);
+    m.io_write(0x02, 1, 0x30);
+    m.io_write(0x83, 1, 0x02);
+    m.io_write(0x03, 1, 3);
+    m.io_write(0x03, 1, 0);
+    m.io_write(0x0A, 1, 0x01);
+
+    m.process_isa_dma();
+
+    assert_eq!(
+        m.read_physical_bytes(0x2_3000, 5),
+        vec![0xA4, 0xA3, 0xA2, 0xA1, 0x00]
+    );
+    assert!(client.borrow().terminal_count);
+    // TC bit for channel 1 in the primary controller's status register.
+    assert_eq!(m.io_read(0x08, 1) & 0x0F, 1 << 1);
+}
+
+#[test]
+fn registration_requires_pc_platform() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        ..Default::default()
+    })
+    .unwrap();
+    let client = Rc::new(RefCell::new(PatternSource::default()));
+    assert_eq!(
+        m.register_isa_dma_client(2, client),
+        Err(MachineError::IsaDmaRequiresPcPlatform)
+    );
+}
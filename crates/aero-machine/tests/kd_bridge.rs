@@ -0,0 +1,163 @@
+use aero_machine::{KdFrameKind, KdPacketFramer, Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+const COM2: u16 = 0x2F8;
+
+fn control_packet(packet_type: u16, packet_id: u32) -> Vec<u8> {
+    let mut packet = vec![0x69; 4];
+    packet.extend_from_slice(&packet_type.to_le_bytes());
+    packet.extend_from_slice(&0u16.to_le_bytes());
+    packet.extend_from_slice(&packet_id.to_le_bytes());
+    packet.extend_from_slice(&0u32.to_le_bytes());
+    packet
+}
+
+fn data_packet(packet_type: u16, packet_id: u32, data: &[u8]) -> Vec<u8> {
+    let checksum: u32 = data.iter().map(|&b| u32::from(b)).sum();
+    let mut packet = vec![0x30; 4];
+    packet.extend_from_slice(&packet_type.to_le_bytes());
+    packet.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    packet.extend_from_slice(&packet_id.to_le_bytes());
+    packet.extend_from_slice(&checksum.to_le_bytes());
+    packet.extend_from_slice(data);
+    packet.push(0xAA);
+    packet
+}
+
+#[test]
+fn framer_delimits_replayed_kd_handshake() {
+    // Guest side of a KDCOM connection: console noise, PACKET_TYPE_KD_RESET (6), a
+    // PACKET_TYPE_KD_STATE_CHANGE64 (7) data packet whose payload contains leader-like bytes, and
+    // PACKET_TYPE_KD_ACKNOWLEDGE (4).
+    let reset = control_packet(6, 0);
+    let state_change = data_packet(7, 0x8080_0000, b"\x30\x30\x30\x69\x69\x69\x69payload");
+    let ack = control_packet(4, 0x8080_0001);
+    let mut stream = b"boot\r\n00".to_vec();
+    stream.extend_from_slice(&reset);
+    stream.extend_from_slice(&state_change);
+    stream.extend_from_slice(&ack);
+
+    let mut framer = KdPacketFramer::new();
+    for (i, chunk) in stream.chunks(3).enumerate() {
+        framer.push(i as u64, chunk);
+    }
+    let frames = framer.take_frames();
+
+    let unframed: Vec<u8> = frames
+        .iter()
+        .filter(|f| f.kind == KdFrameKind::Unframed)
+        .flat_map(|f| f.bytes.clone())
+        .collect();
+    assert_eq!(unframed, b"boot\r\n00");
+    let packets: Vec<(KdFrameKind, Vec<u8>)> = frames
+        .into_iter()
+        .filter(|f| f.kind != KdFrameKind::Unframed)
+        .map(|f| (f.kind, f.bytes))
+        .collect();
+    assert_eq!(
+        packets,
+        vec![
+            (KdFrameKind::Control, reset),
+            (KdFrameKind::Data, state_change),
+            (KdFrameKind::Control, ack),
+        ]
+    );
+}
+
+#[test]
+fn framer_resyncs_after_malformed_packet() {
+    // Data packet with a corrupted trailer, followed by a valid control packet.
+    let mut bad = data_packet(7, 1, b"xy");
+    *bad.last_mut().unwrap() = 0x00;
+    let ack = control_packet(4, 1);
+    let mut stream = bad.clone();
+    stream.extend_from_slice(&ack);
+
+    let mut framer = KdPacketFramer::new();
+    framer.push(5, &stream);
+    let frames = framer.take_frames();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].kind, KdFrameKind::Unframed);
+    assert_eq!(frames[0].bytes, bad);
+    assert_eq!(frames[1].kind, KdFrameKind::Control);
+    assert_eq!(frames[1].bytes, ack);
+    assert_eq!(frames[1].time_ns, 5);
+}
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+#[test]
+fn machine_bridges_com2_packets_in_both_directions() {
+    let packet = control_packet(6, 0);
+    // mov si, 0x7C20; mov cx, len; mov dx, 0x2F8; cld; rep outsb; cli; hlt
+    let mut code = vec![
+        0xBE,
+        0x20,
+        0x7C,
+        0xB9,
+        packet.len() as u8,
+        0x00,
+        0xBA,
+        0xF8,
+        0x02,
+    ];
+    code.extend_from_slice(&[0xFC, 0xF3, 0x6E, 0xFA, 0xF4]);
+    code.resize(0x20, 0x90);
+    code.extend_from_slice(&packet);
+
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_com2: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(&code)).unwrap();
+    m.reset();
+    assert!(!m.enable_kd_bridge(2));
+    assert!(m.enable_kd_bridge(1));
+    m.kd_set_packet_framing(true);
+
+    let mut halted = false;
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => {
+                halted = true;
+                break;
+            }
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    assert!(halted);
+
+    let frame = m.kd_next_frame().expect("guest packet should be framed");
+    assert_eq!(frame.kind, KdFrameKind::Control);
+    assert_eq!(frame.bytes, packet);
+    assert_eq!(m.kd_next_frame(), None);
+    assert_eq!(m.serial_port_output_len(1), 0);
+
+    // Debugger -> guest bytes land in the UART receive FIFO.
+    assert!(m.kd_send_to_guest(b"b"));
+    assert_eq!(m.io_read(COM2 + 5, 1) & 0x01, 0x01);
+    assert_eq!(m.io_read(COM2, 1), u32::from(b'b'));
+
+    // Without framing, output is returned as timestamped raw chunks.
+    m.kd_set_packet_framing(false);
+    m.io_write(COM2, 1, u32::from(b'Z'));
+    let chunks = m.kd_take_from_guest();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].bytes, b"Z");
+    assert!(chunks[0].time_ns >= frame.time_ns);
+}
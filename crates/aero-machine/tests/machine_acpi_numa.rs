@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use aero_machine::{Machine, MachineConfig, MachineError, NumaNodeConfig};
+use firmware::bios::PCIE_ECAM_BASE;
+use pretty_assertions::assert_eq;
+
+const MIB: u64 = 1024 * 1024;
+
+// The hole-crossing test configures >2.75GiB of guest RAM; keep such VMs from running in parallel.
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) == 0
+}
+
+fn read_u32_le(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+fn read_u64_le(buf: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(buf[off..off + 8].try_into().unwrap())
+}
+
+fn read_sdt(m: &mut Machine, addr: u64) -> Vec<u8> {
+    let hdr = m.read_physical_bytes(addr, 36);
+    let len = read_u32_le(&hdr, 4) as usize;
+    assert!(len >= 36, "ACPI SDT length too small: {len}");
+    let table = m.read_physical_bytes(addr, len);
+    assert!(
+        checksum_ok(&table),
+        "ACPI SDT checksum invalid for {:?} at 0x{addr:x}",
+        &table[0..4]
+    );
+    table
+}
+
+/// Find the table with `signature` through the RSDP -> XSDT chain in guest memory.
+fn find_table(m: &mut Machine, signature: &[u8; 4]) -> Option<Vec<u8>> {
+    let rsdp_addr = m.acpi_rsdp_addr().expect("ACPI RSDP should be published");
+    let rsdp = m.read_physical_bytes(rsdp_addr, 36);
+    let xsdt = read_sdt(m, read_u64_le(&rsdp, 24));
+    assert_eq!(&xsdt[0..4], b"XSDT");
+    xsdt[36..]
+        .chunks_exact(8)
+        .map(|ent| u64::from_le_bytes(ent.try_into().unwrap()))
+        .map(|addr| read_sdt(m, addr))
+        .find(|table| &table[0..4] == signature)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Srat {
+    /// APIC ID -> proximity domain.
+    cpus: BTreeMap<u8, u32>,
+    /// `(domain, base, len)` in table order.
+    memory: Vec<(u32, u64, u64)>,
+}
+
+fn parse_srat(srat: &[u8]) -> Srat {
+    let mut out = Srat::default();
+    let mut off = 48;
+    while off < srat.len() {
+        let len = srat[off + 1] as usize;
+        assert!(
+            len >= 2 && off + len <= srat.len(),
+            "bad SRAT entry at {off}"
+        );
+        match srat[off] {
+            0 => {
+                let domain = u32::from(srat[off + 2])
+                    | u32::from_le_bytes([0, srat[off + 9], srat[off + 10], srat[off + 11]]);
+                assert_eq!(read_u32_le(srat, off + 4) & 1, 1, "processor enabled");
+                assert_eq!(out.cpus.insert(srat[off + 3], domain), None);
+            }
+            1 => {
+                assert_eq!(read_u32_le(srat, off + 28) & 1, 1, "memory enabled");
+                out.memory.push((
+                    read_u32_le(srat, off + 2),
+                    read_u64_le(srat, off + 8),
+                    read_u64_le(srat, off + 16),
+                ));
+            }
+            _ => {}
+        }
+        off += len;
+    }
+    out
+}
+
+fn madt_apic_ids(madt: &[u8]) -> Vec<u8> {
+    let mut ids = Vec::new();
+    let mut off = 44;
+    while off < madt.len() {
+        let len = madt[off + 1] as usize;
+        if madt[off] == 0 {
+            ids.push(madt[off + 3]);
+        }
+        off += len;
+    }
+    ids
+}
+
+fn numa_cfg(ram_size_bytes: u64, cpu_count: u8, numa_nodes: Vec<NumaNodeConfig>) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes,
+        cpu_count,
+        numa_nodes: Some(numa_nodes),
+        enable_pc_platform: true,
+        enable_acpi: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn numa_tables_are_absent_without_topology() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * MIB,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(find_table(&mut m, b"SRAT").is_none());
+    assert!(find_table(&mut m, b"SLIT").is_none());
+}
+
+#[test]
+fn srat_and_slit_describe_the_configured_topology() {
+    let mut m = Machine::new(numa_cfg(
+        64 * MIB,
+        4,
+        vec![
+            NumaNodeConfig {
+                cpus: vec![0, 2],
+                mem_bytes: 16 * MIB,
+            },
+            NumaNodeConfig {
+                cpus: vec![1, 3],
+                mem_bytes: 48 * MIB,
+            },
+        ],
+    ))
+    .unwrap();
+
+    let srat = parse_srat(&find_table(&mut m, b"SRAT").expect("SRAT should be published"));
+    assert_eq!(srat.cpus, BTreeMap::from([(0, 0), (1, 1), (2, 0), (3, 1)]));
+    assert_eq!(srat.memory, vec![(0, 0, 16 * MIB), (1, 16 * MIB, 48 * MIB)]);
+    let total: u64 = srat.memory.iter().map(|&(_, _, len)| len).sum();
+    assert_eq!(total, 64 * MIB);
+
+    // The MADT publishes exactly the APIC IDs the SRAT assigns to nodes.
+    let madt = find_table(&mut m, b"APIC").unwrap();
+    assert_eq!(
+        madt_apic_ids(&madt),
+        srat.cpus.keys().copied().collect::<Vec<_>>()
+    );
+
+    let slit = find_table(&mut m, b"SLIT").expect("SLIT should be published");
+    assert_eq!(read_u64_le(&slit, 36), 2);
+    assert_eq!(&slit[44..], &[10, 20, 20, 10]);
+
+    // The topology is platform wiring and is republished by every POST.
+    m.reset();
+    let again = parse_srat(&find_table(&mut m, b"SRAT").unwrap());
+    assert_eq!(again, srat);
+}
+
+#[test]
+fn srat_memory_ranges_follow_the_high_ram_remap() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let ram_size_bytes = PCIE_ECAM_BASE + 64 * MIB;
+    let node0 = PCIE_ECAM_BASE - 256 * MIB;
+    let mut m = Machine::new(MachineConfig {
+        enable_serial: false,
+        enable_i8042: false,
+        ..numa_cfg(
+            ram_size_bytes,
+            2,
+            vec![
+                NumaNodeConfig {
+                    cpus: vec![0],
+                    mem_bytes: node0,
+                },
+                NumaNodeConfig {
+                    cpus: vec![1],
+                    mem_bytes: ram_size_bytes - node0,
+                },
+            ],
+        )
+    })
+    .unwrap();
+
+    let srat = parse_srat(&find_table(&mut m, b"SRAT").unwrap());
+    // Node 1 straddles the PCI/ECAM hole: its tail is reported at the remapped address above 4GiB.
+    assert_eq!(
+        srat.memory,
+        vec![
+            (0, 0, node0),
+            (1, node0, 256 * MIB),
+            (1, 0x1_0000_0000, 64 * MIB),
+        ]
+    );
+    let total: u64 = srat.memory.iter().map(|&(_, _, len)| len).sum();
+    assert_eq!(total, ram_size_bytes);
+}
+
+#[test]
+fn invalid_topologies_are_rejected() {
+    let node = |cpus: Vec<u8>, mem_bytes| NumaNodeConfig { cpus, mem_bytes };
+    let cases = [
+        // CPU 1 is not assigned.
+        numa_cfg(16 * MIB, 2, vec![node(vec![0], 16 * MIB)]),
+        // CPU 2 does not exist.
+        numa_cfg(16 * MIB, 2, vec![node(vec![0, 1, 2], 16 * MIB)]),
+        // CPU 0 is assigned twice.
+        numa_cfg(
+            16 * MIB,
+            2,
+            vec![node(vec![0, 1], 8 * MIB), node(vec![0], 8 * MIB)],
+        ),
+        // Memory does not add up to the RAM size.
+        numa_cfg(16 * MIB, 1, vec![node(vec![0], 8 * MIB)]),
+        // Memory is not 1MiB granular.
+        numa_cfg(
+            16 * MIB,
+            1,
+            vec![node(vec![0], 8 * MIB + 4096), node(vec![], 8 * MIB - 4096)],
+        ),
+        // No nodes at all.
+        numa_cfg(16 * MIB, 1, vec![]),
+        // No ACPI to publish the tables through.
+        MachineConfig {
+            enable_acpi: false,
+            ..numa_cfg(16 * MIB, 1, vec![node(vec![0], 16 * MIB)])
+        },
+    ];
+    for cfg in cases {
+        let nodes = cfg.numa_nodes.clone();
+        let err = Machine::new(cfg).err();
+        assert!(
+            matches!(err, Some(MachineError::InvalidNumaTopology(_))),
+            "{nodes:?}: {err:?}"
+        );
+    }
+}
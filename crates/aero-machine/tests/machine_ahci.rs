@@ -434,7 +434,8 @@ fn machine_exposes_ich9_ahci_at_canonical_bdf_and_bar5_mmio_works() {
     let cap = m.read_physical_u32(bar5_base);
     let pi = m.read_physical_u32(bar5_base + 0x0C);
 
-    assert_eq!(cap, 0x8000_1F00);
+    // S64A | SCLO | ISS=Gen1 | SAM | NCS=31, single port.
+    assert_eq!(cap, 0x8114_1F00);
     assert_eq!(pi, 0x0000_0001);
 }
 
@@ -0,0 +1,118 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_storage::{MemBackend, RawDisk, SECTOR_SIZE};
+use firmware::bios::BootEntry;
+
+const FLOPPY_SECTORS: usize = 2880;
+
+fn machine(boot_order: Vec<BootEntry>) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        boot_order,
+        enable_pc_platform: false,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Boot sector that stores `marker` at 0000:0500 and halts.
+fn boot_sector(marker: u8) -> Vec<u8> {
+    let mut sector = vec![0u8; SECTOR_SIZE];
+    let code = [
+        0xC6, 0x06, 0x00, 0x05, marker, // mov byte [0x0500], marker
+        0xF4,   // hlt
+        0xEB, 0xFD, // jmp short $-3
+    ];
+    sector[..code.len()].copy_from_slice(&code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn floppy_with_boot_sector(marker: u8) -> Box<RawDisk<MemBackend>> {
+    let mut bytes = vec![0u8; FLOPPY_SECTORS * SECTOR_SIZE];
+    bytes[..SECTOR_SIZE].copy_from_slice(&boot_sector(marker));
+    Box::new(RawDisk::open(MemBackend::from_vec(bytes)).unwrap())
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } if !m.boot_menu_active() => return,
+            RunExit::Halted { .. } | RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("machine did not halt within slice budget");
+}
+
+#[test]
+fn boot_order_falls_through_unbootable_hdd_to_floppy() {
+    let mut m = machine(vec![BootEntry::Hdd(0), BootEntry::Floppy]);
+    // HDD without a boot signature.
+    m.set_disk_image(vec![0u8; SECTOR_SIZE]).unwrap();
+    m.attach_floppy_image(floppy_with_boot_sector(0xF1))
+        .unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+
+    assert_eq!(m.booted_entry(), Some(BootEntry::Floppy));
+    assert_eq!(m.read_physical_u8(0x500), 0xF1);
+}
+
+#[test]
+fn boot_menu_selects_entry_from_injected_key() {
+    let mut m = machine(Vec::new());
+    m.set_disk_image(boot_sector(0xD0)).unwrap();
+    m.attach_floppy_image(floppy_with_boot_sector(0xF1))
+        .unwrap();
+    m.request_boot_menu();
+    m.reset();
+
+    assert!(m.boot_menu_active());
+    assert!(matches!(
+        m.run_slice(1_000),
+        RunExit::Halted { executed: 0 }
+    ));
+    assert!(m.boot_menu_active());
+    assert_eq!(m.booted_entry(), None);
+
+    // Menu order follows the configured HDD boot drive: 1 = HDD0, 2 = floppy.
+    m.inject_bios_key(0x0332);
+    run_until_halt(&mut m);
+    assert_eq!(m.booted_entry(), Some(BootEntry::Floppy));
+    assert_eq!(m.read_physical_u8(0x500), 0xF1);
+
+    // The request is one-shot: the next reset boots the configured order directly.
+    m.reset();
+    assert!(!m.boot_menu_active());
+    run_until_halt(&mut m);
+    assert_eq!(m.booted_entry(), Some(BootEntry::Hdd(0)));
+    assert_eq!(m.read_physical_u8(0x500), 0xD0);
+}
+
+#[test]
+fn boot_menu_timeout_falls_back_to_configured_order() {
+    let mut m = machine(vec![BootEntry::Floppy, BootEntry::Hdd(0)]);
+    m.set_disk_image(boot_sector(0xD0)).unwrap();
+    m.attach_floppy_image(floppy_with_boot_sector(0xF1))
+        .unwrap();
+    m.set_boot_menu_timeout_ms(5);
+    m.request_boot_menu();
+    m.reset();
+
+    for _ in 0..4 {
+        assert!(matches!(m.run_slice(1_000), RunExit::Halted { .. }));
+        assert!(m.boot_menu_active());
+    }
+    run_until_halt(&mut m);
+    assert_eq!(m.booted_entry(), Some(BootEntry::Floppy));
+    assert_eq!(m.read_physical_u8(0x500), 0xF1);
+}
@@ -0,0 +1,223 @@
+use aero_machine::{
+    ConfigDelta, GpuKind, Machine, MachineConfig, MachineConfigBuilder, MachineError, NicKind,
+    StorageTopology, UsbControllers, VirtioInputKind,
+};
+use pretty_assertions::assert_eq;
+
+const RAM: u64 = 256 * 1024 * 1024;
+
+fn build_err(builder: MachineConfigBuilder) -> MachineError {
+    match builder.build() {
+        Ok(cfg) => panic!("expected build() to reject config: {cfg:?}"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn pci_devices_without_pc_platform_are_rejected_at_build() {
+    let headless = || MachineConfig::builder().with_gpu(GpuKind::None);
+    let cases: Vec<(MachineConfigBuilder, MachineError)> = vec![
+        (
+            MachineConfig::builder().with_gpu(GpuKind::AeroGpu),
+            MachineError::AeroGpuRequiresPcPlatform,
+        ),
+        (
+            headless().with_storage(StorageTopology {
+                ahci: true,
+                ..StorageTopology::NONE
+            }),
+            MachineError::AhciRequiresPcPlatform,
+        ),
+        (
+            headless().with_storage(StorageTopology {
+                nvme: true,
+                ..StorageTopology::NONE
+            }),
+            MachineError::NvmeRequiresPcPlatform,
+        ),
+        (
+            headless().with_storage(StorageTopology {
+                ide: true,
+                ..StorageTopology::NONE
+            }),
+            MachineError::IdeRequiresPcPlatform,
+        ),
+        (
+            headless().with_storage(StorageTopology {
+                virtio_blk: true,
+                ..StorageTopology::NONE
+            }),
+            MachineError::VirtioBlkRequiresPcPlatform,
+        ),
+        (
+            headless().with_virtio_input(VirtioInputKind::KeyboardMouseTablet),
+            MachineError::VirtioInputRequiresPcPlatform,
+        ),
+        (
+            headless().with_usb(UsbControllers {
+                uhci: true,
+                ..UsbControllers::NONE
+            }),
+            MachineError::UhciRequiresPcPlatform,
+        ),
+        (
+            headless().with_usb(UsbControllers {
+                ehci: true,
+                ..UsbControllers::NONE
+            }),
+            MachineError::EhciRequiresPcPlatform,
+        ),
+        (
+            headless().with_usb(UsbControllers {
+                xhci: true,
+                ..UsbControllers::NONE
+            }),
+            MachineError::XhciRequiresPcPlatform,
+        ),
+        (
+            headless().with_nic(NicKind::E1000 { mac_addr: None }),
+            MachineError::E1000RequiresPcPlatform,
+        ),
+        (
+            headless().with_nic(NicKind::VirtioNet { mac_addr: None }),
+            MachineError::VirtioNetRequiresPcPlatform,
+        ),
+    ];
+
+    for (builder, expected) in cases {
+        assert_eq!(build_err(builder.clone()), expected);
+        // Enabling the platform is the only fix needed.
+        builder.with_pc_platform(true).build().unwrap();
+    }
+}
+
+#[test]
+fn other_invalid_combinations_are_rejected_at_build() {
+    assert_eq!(
+        build_err(MachineConfig::builder().with_cpu_count(0)),
+        MachineError::InvalidCpuCount(0)
+    );
+    assert_eq!(
+        build_err(
+            MachineConfig::builder()
+                .with_pc_platform(true)
+                .with_usb(UsbControllers {
+                    synthetic_usb_hid: true,
+                    ..UsbControllers::NONE
+                })
+        ),
+        MachineError::SyntheticUsbHidRequiresUhci
+    );
+
+    // Invalid states carried over from a hand-built struct are still caught.
+    let both_nics = MachineConfig {
+        enable_pc_platform: true,
+        enable_e1000: true,
+        enable_virtio_net: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        build_err(MachineConfigBuilder::from(both_nics.clone())),
+        MachineError::MultipleNicsEnabled
+    );
+    let aerogpu_and_vga = MachineConfig {
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        build_err(MachineConfigBuilder::from(aerogpu_and_vga.clone())),
+        MachineError::AeroGpuConflictsWithVga
+    );
+    let tablet_only = MachineConfig {
+        enable_pc_platform: true,
+        enable_virtio_input_tablet: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        build_err(MachineConfigBuilder::from(tablet_only.clone())),
+        MachineError::VirtioInputTabletRequiresVirtioInput
+    );
+
+    // ...and the typed setters repair them, since the exclusive choices can't be expressed.
+    let cfg = MachineConfigBuilder::from(both_nics)
+        .with_nic(NicKind::VirtioNet {
+            mac_addr: Some([0x52, 0x54, 0, 0x12, 0x34, 0x56]),
+        })
+        .build()
+        .unwrap();
+    assert!(!cfg.enable_e1000);
+    assert!(cfg.enable_virtio_net);
+    assert_eq!(
+        cfg.virtio_net_mac_addr,
+        Some([0x52, 0x54, 0, 0x12, 0x34, 0x56])
+    );
+
+    let cfg = MachineConfigBuilder::from(aerogpu_and_vga)
+        .with_gpu(GpuKind::AeroGpu)
+        .build()
+        .unwrap();
+    assert!(cfg.enable_aerogpu && !cfg.enable_vga);
+
+    let cfg = MachineConfigBuilder::from(tablet_only)
+        .with_virtio_input(VirtioInputKind::KeyboardMouseTablet)
+        .build()
+        .unwrap();
+    assert!(cfg.enable_virtio_input && cfg.enable_virtio_input_tablet);
+}
+
+#[test]
+fn browser_defaults_diff_matches_golden_list() {
+    let delta = |field, before: &str, after: &str| ConfigDelta {
+        field,
+        before: before.to_string(),
+        after: after.to_string(),
+    };
+    assert_eq!(
+        MachineConfig::default().diff(&MachineConfig::browser_defaults(RAM)),
+        vec![
+            delta("ram_size_bytes", "67108864", "268435456"),
+            delta("enable_pc_platform", "false", "true"),
+            delta("enable_acpi", "false", "true"),
+            delta("enable_ahci", "false", "true"),
+            delta("enable_ide", "false", "true"),
+            delta("enable_uhci", "false", "true"),
+            delta("enable_vga", "true", "false"),
+            delta("enable_aerogpu", "false", "true"),
+            delta("enable_e1000", "false", "true"),
+        ]
+    );
+}
+
+#[test]
+fn builder_reproduces_browser_defaults() {
+    let cfg = MachineConfig::builder()
+        .with_ram_size_bytes(RAM)
+        .with_pc_platform(true)
+        .with_acpi(true)
+        .with_storage(StorageTopology::WIN7)
+        .with_nic(NicKind::E1000 { mac_addr: None })
+        .with_usb(UsbControllers {
+            uhci: true,
+            ..UsbControllers::NONE
+        })
+        .with_gpu(GpuKind::AeroGpu)
+        .build()
+        .unwrap();
+    assert_eq!(cfg.diff(&MachineConfig::browser_defaults(RAM)), vec![]);
+    Machine::new(cfg).unwrap();
+}
+
+#[test]
+fn diff_reports_non_flag_fields() {
+    let a = MachineConfig::default();
+    let b = MachineConfigBuilder::from(a.clone())
+        .with_cpu_count(2)
+        .with_nic(NicKind::None)
+        .build()
+        .unwrap();
+    assert_eq!(a.diff(&b), vec![ConfigDelta::new("cpu_count", 1u8, 2u8)]);
+    assert_eq!(a.diff(&b)[0].to_string(), "cpu_count: 1 -> 2");
+    assert_eq!(a.diff(&a), vec![]);
+}
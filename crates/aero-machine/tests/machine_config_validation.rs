@@ -1,5 +1,8 @@
 use aero_gpu_vga::VBE_FRAMEBUFFER_OFFSET;
-use aero_machine::{Machine, MachineConfig, MachineError};
+use aero_machine::{
+    Machine, MachineConfig, MachineError, AEROGPU_VRAM_SIZE_MAX, AEROGPU_VRAM_SIZE_MIN,
+    VIRTIO_BLK_MAX_QUEUES,
+};
 use aero_pc_constants::PCI_MMIO_BASE;
 
 #[test]
@@ -221,6 +224,75 @@ fn enable_synthetic_usb_hid_requires_enable_uhci() {
     );
 }
 
+#[test]
+fn synthetic_usb_hid_on_xhci_requires_enable_xhci() {
+    let cfg = MachineConfig {
+        enable_pc_platform: true,
+        enable_uhci: true,
+        enable_xhci: false,
+        enable_synthetic_usb_hid: true,
+        synthetic_usb_hid_on_xhci: true,
+        ..Default::default()
+    };
+
+    let err = match Machine::new(cfg) {
+        Ok(_) => panic!("synthetic USB HID on xHCI without xHCI must be rejected"),
+        Err(e) => e,
+    };
+    assert!(matches!(err, MachineError::SyntheticUsbHidRequiresXhci));
+    assert!(
+        err.to_string().contains("requires enable_xhci=true"),
+        "unexpected error message: {err}"
+    );
+}
+
+#[test]
+fn aerogpu_vram_size_must_be_power_of_two_within_bounds() {
+    for size in [
+        AEROGPU_VRAM_SIZE_MIN / 2,
+        AEROGPU_VRAM_SIZE_MIN + AEROGPU_VRAM_SIZE_MIN / 2,
+        AEROGPU_VRAM_SIZE_MAX * 2,
+    ] {
+        let cfg = MachineConfig {
+            enable_pc_platform: true,
+            enable_vga: false,
+            enable_aerogpu: true,
+            aerogpu_vram_size_bytes: Some(size),
+            ..Default::default()
+        };
+        let err = match Machine::new(cfg) {
+            Ok(_) => panic!("aerogpu_vram_size_bytes={size:#x} must be rejected"),
+            Err(e) => e,
+        };
+        assert_eq!(err, MachineError::InvalidAeroGpuVramSize(size));
+        assert!(
+            err.to_string().contains("must be a power of two"),
+            "unexpected error message: {err}"
+        );
+    }
+}
+
+#[test]
+fn virtio_blk_num_queues_must_be_within_bounds() {
+    for count in [0, VIRTIO_BLK_MAX_QUEUES + 1] {
+        let cfg = MachineConfig {
+            enable_pc_platform: true,
+            enable_virtio_blk: true,
+            virtio_blk_num_queues: Some(count),
+            ..Default::default()
+        };
+        let err = match Machine::new(cfg) {
+            Ok(_) => panic!("virtio_blk_num_queues={count} must be rejected"),
+            Err(e) => e,
+        };
+        assert_eq!(err, MachineError::InvalidVirtioBlkQueueCount(count));
+        assert!(
+            err.to_string().contains("must be between 1 and"),
+            "unexpected error message: {err}"
+        );
+    }
+}
+
 #[test]
 fn enable_aerogpu_requires_enable_pc_platform() {
     let cfg = MachineConfig {
@@ -0,0 +1,393 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+//! Every 64-bit capable bus master must be able to place its rings and data buffers in RAM the
+//! chipset remaps above 4GiB. Each test puts *all* guest-side structures above the boundary and
+//! checks that the truncated (low 32-bit) alias of each structure stays untouched.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use aero_devices::a20_gate::A20_GATE_PORT;
+use aero_devices::pci::profile::{
+    self, NIC_E1000_82540EM, NVME_CONTROLLER, SATA_AHCI_ICH9, USB_XHCI_QEMU, VIRTIO_BLK,
+};
+use aero_devices::pci::PciBdf;
+use aero_devices::usb::xhci::regs;
+use aero_devices_storage::ata::ATA_CMD_READ_DMA_EXT;
+use aero_machine::{Machine, MachineConfig};
+use aero_net_backend::NetworkBackend;
+use aero_storage::SECTOR_SIZE;
+use aero_usb::xhci::trb::{CompletionCode, Trb, TrbType, TRB_LEN};
+use aero_virtio::devices::blk::{VIRTIO_BLK_S_OK, VIRTIO_BLK_T_IN};
+use aero_virtio::pci::{
+    VIRTIO_F_VERSION_1, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use aero_virtio::queue::{VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
+use pretty_assertions::assert_eq;
+
+/// 6GiB: 3.25GiB of RAM is remapped to `[4GiB, 7.25GiB)`.
+const RAM_SIZE: u64 = 6 * 1024 * 1024 * 1024;
+/// Guest-side DMA structures are allocated from here (6GiB), well inside remapped high RAM.
+const DMA_BASE: u64 = 0x1_8000_0000;
+const DISK_LBA: u64 = 4;
+
+// Serialize the multi-GiB (sparse) VMs; see `hole_aware_high_memory_ram.rs`.
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+fn high_ram_machine(cfg: MachineConfig) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: RAM_SIZE,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..cfg
+    })
+    .unwrap();
+    m.io_write(A20_GATE_PORT, 1, 0x02);
+    m
+}
+
+fn enable_bus_master(m: &mut Machine, bdf: PciBdf) {
+    let cmd = m.pci_read_config(bdf, 0x04, 2).unwrap();
+    m.pci_write_config(bdf, 0x04, 2, cmd | 0x6);
+}
+
+/// Install a disk image whose sector [`DISK_LBA`] carries a marker; returns that sector.
+fn install_disk(m: &mut Machine) -> Vec<u8> {
+    let mut bytes = vec![0u8; 8 * SECTOR_SIZE];
+    let start = DISK_LBA as usize * SECTOR_SIZE;
+    for (i, b) in bytes[start..start + SECTOR_SIZE].iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(13) ^ 0xA5;
+    }
+    let sector = bytes[start..start + SECTOR_SIZE].to_vec();
+    m.set_disk_image(bytes).unwrap();
+    sector
+}
+
+/// Bump allocator over remapped high RAM that remembers every range it handed out.
+struct HighAlloc {
+    next: u64,
+    ranges: Vec<(u64, u64)>,
+}
+
+impl HighAlloc {
+    fn new() -> Self {
+        Self {
+            next: DMA_BASE,
+            ranges: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self, size: u64, align: u64) -> u64 {
+        let addr = (self.next + align - 1) & !(align - 1);
+        self.next = addr + size;
+        self.ranges.push((addr, size));
+        addr
+    }
+
+    /// A device that truncates to 32 bits would have hit these low-RAM aliases instead.
+    fn assert_low_aliases_untouched(&self, m: &mut Machine) {
+        for &(addr, size) in &self.ranges {
+            let alias = addr & 0xFFFF_FFFF;
+            assert!(
+                m.read_physical_bytes(alias, size as usize)
+                    .iter()
+                    .all(|&b| b == 0),
+                "DMA for {addr:#x} landed at its truncated alias {alias:#x}"
+            );
+        }
+    }
+}
+
+#[test]
+fn ahci_command_list_fis_and_prdt_above_4gib() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = high_ram_machine(MachineConfig {
+        enable_ahci: true,
+        ..Default::default()
+    });
+    let expected = install_disk(&mut m);
+
+    let bdf = SATA_AHCI_ICH9.bdf;
+    let abar = m
+        .pci_bar_base(bdf, 5)
+        .expect("AHCI ABAR should be assigned");
+    enable_bus_master(&mut m, bdf);
+
+    let mut mem = HighAlloc::new();
+    let clb = mem.alloc(1024, 1024);
+    let fb = mem.alloc(256, 256);
+    let ctba = mem.alloc(0x80 + 16, 128);
+    let buf = mem.alloc(SECTOR_SIZE as u64, 512);
+
+    let port = abar + 0x100;
+    m.write_physical_u32(port, clb as u32); // PxCLB
+    m.write_physical_u32(port + 0x04, (clb >> 32) as u32); // PxCLBU
+    m.write_physical_u32(port + 0x08, fb as u32); // PxFB
+    m.write_physical_u32(port + 0x0C, (fb >> 32) as u32); // PxFBU
+    m.write_physical_u32(abar + 0x04, 1 << 31); // GHC.AE
+    m.write_physical_u32(port + 0x18, (1 << 4) | 1); // PxCMD.FRE | PxCMD.ST
+
+    // Slot 0: 5-dword CFIS, device-to-host, one PRDT entry.
+    m.write_physical_u32(clb, 5 | (1 << 16));
+    m.write_physical_u64(clb + 8, ctba);
+    let mut cfis = [0u8; 20];
+    cfis[0] = 0x27; // H2D register FIS
+    cfis[1] = 0x80; // C bit
+    cfis[2] = ATA_CMD_READ_DMA_EXT;
+    cfis[4] = DISK_LBA as u8;
+    cfis[7] = 0x40; // LBA mode
+    cfis[12] = 1; // sector count
+    m.write_physical(ctba, &cfis);
+    m.write_physical_u64(ctba + 0x80, buf);
+    m.write_physical_u32(ctba + 0x8C, SECTOR_SIZE as u32 - 1);
+
+    m.write_physical_u32(port + 0x38, 1); // PxCI
+    for _ in 0..16 {
+        m.process_ahci();
+        if m.read_physical_u32(port + 0x38) == 0 {
+            break;
+        }
+    }
+    assert_eq!(m.read_physical_u32(port + 0x38), 0);
+
+    assert_eq!(m.read_physical_bytes(buf, SECTOR_SIZE), expected);
+    assert_eq!(m.read_physical_u32(clb + 4), SECTOR_SIZE as u32, "PRDBC");
+    assert_eq!(m.read_physical_u8(fb + 0x40), 0x34, "D2H FIS");
+    mem.assert_low_aliases_untouched(&mut m);
+}
+
+#[test]
+fn nvme_queues_and_prps_above_4gib() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = high_ram_machine(MachineConfig {
+        enable_nvme: true,
+        ..Default::default()
+    });
+    let expected = install_disk(&mut m);
+
+    let bdf = NVME_CONTROLLER.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, 0)
+        .expect("NVMe BAR0 should be assigned");
+    enable_bus_master(&mut m, bdf);
+
+    let mut mem = HighAlloc::new();
+    let asq = mem.alloc(4096, 4096);
+    let acq = mem.alloc(4096, 4096);
+    let io_sq = mem.alloc(4096, 4096);
+    let io_cq = mem.alloc(4096, 4096);
+    let buf = mem.alloc(4096, 4096);
+
+    m.write_physical_u32(bar0 + 0x24, 0x000f_000f); // AQA: 16-entry admin SQ/CQ
+    m.write_physical_u64(bar0 + 0x28, asq);
+    m.write_physical_u64(bar0 + 0x30, acq);
+    m.write_physical_u32(bar0 + 0x14, 1); // CC.EN
+
+    let command = |opc: u8, cid: u16, prp1: u64, cdw10: u32, cdw11: u32| {
+        let mut cmd = [0u8; 64];
+        cmd[0] = opc;
+        cmd[2..4].copy_from_slice(&cid.to_le_bytes());
+        cmd[24..32].copy_from_slice(&prp1.to_le_bytes());
+        cmd[40..44].copy_from_slice(&cdw10.to_le_bytes());
+        cmd[44..48].copy_from_slice(&cdw11.to_le_bytes());
+        cmd
+    };
+
+    // Create IO CQ 1 (16 entries, physically contiguous) then IO SQ 1 bound to it.
+    m.write_physical(asq, &command(0x05, 1, io_cq, (15 << 16) | 1, 0x1));
+    m.write_physical(asq + 64, &command(0x01, 2, io_sq, (15 << 16) | 1, 1));
+    m.write_physical_u32(bar0 + 0x1000, 2); // SQ0 tail
+    m.process_nvme();
+    for (i, cid) in [1u16, 2].into_iter().enumerate() {
+        let cqe = acq + i as u64 * 16;
+        assert_eq!(m.read_physical_u16(cqe + 12), cid);
+        assert_eq!(m.read_physical_u16(cqe + 14) & 0xFFFE, 0, "admin cid {cid}");
+    }
+    m.write_physical_u32(bar0 + 0x1004, 2); // CQ0 head
+
+    // READ one block at DISK_LBA into `buf`.
+    let mut read = command(0x02, 0x10, buf, DISK_LBA as u32, 0);
+    read[4..8].copy_from_slice(&1u32.to_le_bytes()); // NSID
+    m.write_physical(io_sq, &read);
+    m.write_physical_u32(bar0 + 0x1008, 1); // SQ1 tail
+    m.process_nvme();
+
+    assert_eq!(m.read_physical_u16(io_cq + 12), 0x10);
+    assert_eq!(m.read_physical_u16(io_cq + 14) & 0xFFFE, 0);
+    assert_eq!(m.read_physical_bytes(buf, SECTOR_SIZE), expected);
+    mem.assert_low_aliases_untouched(&mut m);
+}
+
+#[test]
+fn virtio_blk_virtqueue_and_buffers_above_4gib() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = high_ram_machine(MachineConfig {
+        enable_virtio_blk: true,
+        ..Default::default()
+    });
+    let expected = install_disk(&mut m);
+
+    let bdf = VIRTIO_BLK.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, 0)
+        .expect("virtio-blk BAR0 should be assigned");
+    enable_bus_master(&mut m, bdf);
+
+    let mut mem = HighAlloc::new();
+    let queue_size = 128u64;
+    let desc = mem.alloc(queue_size * 16, 16);
+    let avail = mem.alloc(4 + queue_size * 2, 2);
+    let used = mem.alloc(4 + queue_size * 8, 4);
+    let hdr = mem.alloc(16, 16);
+    let data = mem.alloc(SECTOR_SIZE as u64, 512);
+    let status = mem.alloc(1, 1);
+
+    let common = bar0 + u64::from(profile::VIRTIO_COMMON_CFG_BAR0_OFFSET);
+    let notify = bar0 + u64::from(profile::VIRTIO_NOTIFY_CFG_BAR0_OFFSET);
+    let driver = VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER;
+    m.write_physical_u8(common + 0x14, driver);
+    m.write_physical_u32(common + 0x08, 1); // driver_feature_select = 1
+    m.write_physical_u32(common + 0x0c, (VIRTIO_F_VERSION_1 >> 32) as u32);
+    m.write_physical_u8(common + 0x14, driver | VIRTIO_STATUS_FEATURES_OK);
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, desc);
+    m.write_physical_u64(common + 0x28, avail);
+    m.write_physical_u64(common + 0x30, used);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+    m.write_physical_u8(
+        common + 0x14,
+        driver | VIRTIO_STATUS_FEATURES_OK | VIRTIO_STATUS_DRIVER_OK,
+    );
+
+    m.write_physical_u32(hdr, VIRTIO_BLK_T_IN);
+    m.write_physical_u64(hdr + 8, DISK_LBA);
+    let chain = [
+        (hdr, 16, VIRTQ_DESC_F_NEXT),
+        (
+            data,
+            SECTOR_SIZE as u32,
+            VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE,
+        ),
+        (status, 1, VIRTQ_DESC_F_WRITE),
+    ];
+    for (i, (addr, len, flags)) in chain.into_iter().enumerate() {
+        let entry = desc + i as u64 * 16;
+        m.write_physical_u64(entry, addr);
+        m.write_physical_u32(entry + 8, len);
+        m.write_physical_u16(entry + 12, flags);
+        m.write_physical_u16(entry + 14, i as u16 + 1);
+    }
+    m.write_physical_u8(status, 0xFF);
+    m.write_physical_u16(avail + 4, 0); // ring[0] = head 0
+    m.write_physical_u16(avail + 2, 1); // avail.idx
+
+    m.write_physical_u32(notify, 0);
+    m.process_virtio_blk();
+
+    assert_eq!(m.read_physical_u16(used + 2), 1);
+    assert_eq!(m.read_physical_u8(status), VIRTIO_BLK_S_OK);
+    assert_eq!(m.read_physical_bytes(data, SECTOR_SIZE), expected);
+    mem.assert_low_aliases_untouched(&mut m);
+}
+
+struct CaptureBackend(Rc<RefCell<Vec<Vec<u8>>>>);
+
+impl NetworkBackend for CaptureBackend {
+    fn transmit(&mut self, frame: Vec<u8>) {
+        self.0.borrow_mut().push(frame);
+    }
+}
+
+#[test]
+fn e1000_tx_ring_and_packet_above_4gib() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = high_ram_machine(MachineConfig {
+        enable_e1000: true,
+        ..Default::default()
+    });
+    let frames = Rc::new(RefCell::new(Vec::new()));
+    m.set_network_backend(Box::new(CaptureBackend(frames.clone())));
+
+    let bdf = NIC_E1000_82540EM.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, 0)
+        .expect("E1000 BAR0 should be assigned");
+    enable_bus_master(&mut m, bdf);
+
+    let mut mem = HighAlloc::new();
+    let ring = mem.alloc(16 * 4, 128);
+    let pkt_addr = mem.alloc(60, 16);
+    let pkt: Vec<u8> = (0..60u8).collect();
+    m.write_physical(pkt_addr, &pkt);
+
+    // Legacy TX descriptor 0: buffer, length, CMD = EOP | RS.
+    m.write_physical_u64(ring, pkt_addr);
+    m.write_physical_u16(ring + 8, pkt.len() as u16);
+    m.write_physical_u8(ring + 11, 0b0000_1001);
+
+    m.write_physical_u32(bar0 + 0x3800, ring as u32); // TDBAL
+    m.write_physical_u32(bar0 + 0x3804, (ring >> 32) as u32); // TDBAH
+    m.write_physical_u32(bar0 + 0x3808, 16 * 4); // TDLEN
+    m.write_physical_u32(bar0 + 0x3810, 0); // TDH
+    m.write_physical_u32(bar0 + 0x3818, 0); // TDT
+    m.write_physical_u32(bar0 + 0x0400, 1 << 1); // TCTL.EN
+    m.write_physical_u32(bar0 + 0x3818, 1);
+    m.poll_network();
+
+    assert_eq!(*frames.borrow(), vec![pkt]);
+    assert_eq!(
+        m.read_physical_u8(ring + 12) & 1,
+        1,
+        "descriptor DD writeback"
+    );
+    mem.assert_low_aliases_untouched(&mut m);
+}
+
+#[test]
+fn xhci_command_and_event_rings_above_4gib() {
+    let _guard = TEST_LOCK.lock().unwrap();
+    let mut m = high_ram_machine(MachineConfig {
+        enable_xhci: true,
+        ..Default::default()
+    });
+
+    let bdf = USB_XHCI_QEMU.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, 0)
+        .expect("xHCI BAR0 should be assigned");
+    enable_bus_master(&mut m, bdf);
+
+    let mut mem = HighAlloc::new();
+    let cmd_ring = mem.alloc(2 * TRB_LEN as u64, 64);
+    let erst = mem.alloc(16, 64);
+    let event_ring = mem.alloc(256 * TRB_LEN as u64, 64);
+
+    m.write_physical_u64(erst, event_ring);
+    m.write_physical_u32(erst + 8, 256);
+    m.write_physical_u32(bar0 + regs::REG_INTR0_ERSTSZ, 1);
+    m.write_physical_u64(bar0 + regs::REG_INTR0_ERSTBA_LO, erst);
+    m.write_physical_u64(bar0 + regs::REG_INTR0_ERDP_LO, event_ring);
+    m.write_physical_u64(bar0 + regs::REG_CRCR_LO, cmd_ring | 1);
+    m.write_physical_u32(bar0 + regs::REG_USBCMD, regs::USBCMD_RUN);
+
+    let mut noop = Trb::new(0, 0, 0);
+    noop.set_trb_type(TrbType::NoOpCommand);
+    noop.set_cycle(true);
+    m.write_physical(cmd_ring, &noop.to_bytes());
+    m.write_physical_u32(bar0 + u64::from(regs::DBOFF_VALUE), 0);
+    m.tick_platform(10_000_000);
+
+    let evt = Trb::from_bytes(
+        m.read_physical_bytes(event_ring, TRB_LEN)
+            .try_into()
+            .unwrap(),
+    );
+    assert_eq!(evt.trb_type(), TrbType::CommandCompletionEvent);
+    assert_eq!(evt.completion_code_raw(), CompletionCode::Success.as_u8());
+    assert_eq!(evt.parameter & !0x0f, cmd_ring);
+    mem.assert_low_aliases_untouched(&mut m);
+}
@@ -0,0 +1,60 @@
+use aero_machine::{Machine, MachineConfig};
+use aero_net_e1000::ICR_LSC;
+
+const REG_STATUS: u64 = 0x0008;
+const REG_ICR: u64 = 0x00C0;
+const REG_IMS: u64 = 0x00D0;
+const STATUS_LU: u32 = 1 << 1;
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_e1000: true,
+        // Keep the machine minimal and deterministic for this test.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn set_nic_link_up_flaps_status_and_raises_lsc() {
+    let mut m = new_machine();
+    let nic = m.e1000().expect("e1000 enabled");
+    assert!(m.nic_link_up());
+    nic.borrow_mut().mmio_write_reg(REG_IMS, 4, ICR_LSC);
+
+    m.set_nic_link_up(false);
+    {
+        let mut dev = nic.borrow_mut();
+        assert!(dev.irq_level());
+        assert_eq!(dev.mmio_read(REG_STATUS, 4) & STATUS_LU, 0);
+        assert_eq!(dev.mmio_read(REG_ICR, 4) & ICR_LSC, ICR_LSC);
+        assert!(!dev.irq_level());
+    }
+
+    m.set_nic_link_up(true);
+    {
+        let mut dev = nic.borrow_mut();
+        assert!(dev.irq_level());
+        assert_ne!(dev.mmio_read(REG_STATUS, 4) & STATUS_LU, 0);
+        assert_eq!(dev.mmio_read(REG_ICR, 4) & ICR_LSC, ICR_LSC);
+    }
+}
+
+#[test]
+fn nic_link_state_survives_machine_reset() {
+    let mut m = new_machine();
+    m.set_nic_link_up(false);
+    m.reset();
+
+    assert!(!m.nic_link_up());
+    let nic = m.e1000().expect("e1000 enabled");
+    let mut dev = nic.borrow_mut();
+    assert!(!dev.link_up());
+    assert_eq!(dev.mmio_read(REG_STATUS, 4) & STATUS_LU, 0);
+}
@@ -0,0 +1,170 @@
+use aero_cpu_core::state::{gpr, RFLAGS_IF};
+use aero_machine::{
+    DirectBootMode, DirectBootSpec, InjectEventError, Machine, MachineConfig, RunExit,
+    DIRECT_BOOT_CODE32_SELECTOR,
+};
+use pretty_assertions::assert_eq;
+
+const IDT_BASE: u64 = 0x3000;
+const IDTR_ADDR: u64 = 0x2F00;
+const FLAG_BASE: u64 = 0x4000;
+const CODE_BASE: u64 = 0x10_0000;
+const HANDLER_BASE: u64 = 0x10_1000;
+/// Vectors with an installed handler; the handler for vector `v` writes `v + 1` to
+/// `FLAG_BASE + v`.
+const HANDLED_VECTORS: [u8; 3] = [1, 2, 0x40];
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn handler_addr(vector: u8) -> u64 {
+    HANDLER_BASE + u64::from(vector) * 0x10
+}
+
+/// Boot into flat 32-bit protected mode with `IF=0`, load an IDT with handlers for
+/// [`HANDLED_VECTORS`] and park in a `hlt` loop.
+fn boot_guest_with_idt(m: &mut Machine) {
+    let mut blobs = Vec::new();
+
+    // lidt [IDTR_ADDR]; hlt; jmp short -3
+    let mut code = vec![0x0F, 0x01, 0x1D];
+    code.extend_from_slice(&(IDTR_ADDR as u32).to_le_bytes());
+    code.extend_from_slice(&[0xF4, 0xEB, 0xFD]);
+    blobs.push((CODE_BASE, code));
+
+    let idt_limit: u16 = 0x41 * 8 - 1;
+    let mut idtr = idt_limit.to_le_bytes().to_vec();
+    idtr.extend_from_slice(&(IDT_BASE as u32).to_le_bytes());
+    blobs.push((IDTR_ADDR, idtr));
+
+    for vector in HANDLED_VECTORS {
+        let handler = handler_addr(vector) as u32;
+        // 32-bit interrupt gate, DPL0, present.
+        let low = (u32::from(DIRECT_BOOT_CODE32_SELECTOR) << 16) | (handler & 0xFFFF);
+        let high = (handler & 0xFFFF_0000) | 0x8E00;
+        let mut gate = low.to_le_bytes().to_vec();
+        gate.extend_from_slice(&high.to_le_bytes());
+        blobs.push((IDT_BASE + u64::from(vector) * 8, gate));
+
+        // mov byte [FLAG_BASE + vector], vector + 1; iretd
+        let mut handler_code = vec![0xC6, 0x05];
+        handler_code.extend_from_slice(&((FLAG_BASE + u64::from(vector)) as u32).to_le_bytes());
+        handler_code.extend_from_slice(&[vector + 1, 0xCF]);
+        blobs.push((handler_addr(vector), handler_code));
+    }
+
+    let mut regs = [0u64; 16];
+    regs[gpr::RSP] = 0x8000;
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Protected32,
+        entry: CODE_BASE,
+        gpr: regs,
+        blobs,
+        ..Default::default()
+    })
+    .unwrap();
+    run_until_halt(m);
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+fn flag(m: &mut Machine, vector: u8) -> u8 {
+    m.read_physical_u8(FLAG_BASE + u64::from(vector))
+}
+
+#[test]
+fn injected_debug_exception_runs_idt_handler() {
+    let mut m = new_machine();
+    boot_guest_with_idt(&mut m);
+    assert_eq!(flag(&mut m, 1), 0);
+
+    m.inject_exception(0, 1, None).unwrap();
+    run_until_halt(&mut m);
+    assert_eq!(flag(&mut m, 1), 2);
+    assert!(m.cpu().halted);
+}
+
+#[test]
+fn injected_nmi_is_delivered_with_interrupts_disabled() {
+    let mut m = new_machine();
+    boot_guest_with_idt(&mut m);
+    assert_eq!(m.cpu().rflags() & RFLAGS_IF, 0);
+
+    m.inject_nmi(0).unwrap();
+    run_until_halt(&mut m);
+    assert_eq!(flag(&mut m, 2), 3);
+    assert_eq!(m.perf_counters().interrupt_injections, 1);
+}
+
+#[test]
+fn injected_interrupt_vector_waits_for_if() {
+    let mut m = new_machine();
+    boot_guest_with_idt(&mut m);
+
+    m.inject_exception(0, 0x40, None).unwrap();
+    run_until_halt(&mut m);
+    assert_eq!(flag(&mut m, 0x40), 0);
+
+    let rflags = m.cpu().rflags();
+    m.cpu_mut().set_rflags(rflags | RFLAGS_IF);
+    run_until_halt(&mut m);
+    assert_eq!(flag(&mut m, 0x40), 0x41);
+}
+
+#[test]
+fn inject_rejects_invalid_cpu_and_reserved_vectors() {
+    let mut m = new_machine();
+    assert_eq!(m.inject_nmi(1), Err(InjectEventError::InvalidCpuIndex(1)));
+    assert_eq!(
+        m.inject_exception(0, 15, None),
+        Err(InjectEventError::ReservedVector(15))
+    );
+    assert_eq!(
+        m.inject_exception(2, 13, Some(0)),
+        Err(InjectEventError::InvalidCpuIndex(2))
+    );
+}
+
+#[test]
+fn smi_stub_is_reported_as_unsupported_once() {
+    let mut m = new_machine();
+    boot_guest_with_idt(&mut m);
+
+    m.inject_smi_stub();
+    assert!(matches!(
+        m.run_slice(10_000),
+        RunExit::SmiUnsupported { executed: 0 }
+    ));
+    assert!(matches!(m.run_slice(10_000), RunExit::Halted { .. }));
+}
+
+#[test]
+fn reset_drops_queued_events() {
+    let mut m = new_machine();
+    m.inject_smi_stub();
+    m.inject_nmi(0).unwrap();
+    m.reset();
+    assert!(!matches!(
+        m.run_slice(1_000),
+        RunExit::SmiUnsupported { .. }
+    ));
+}
@@ -0,0 +1,249 @@
+use aero_machine::{
+    BootStage, Machine, MachineConfig, MachineEvent, MachineEventCategory, MachineEventKind,
+    MachineEventMask, ResetSource, RunExit, UnknownAccessSpace,
+};
+use pretty_assertions::assert_eq;
+
+/// Alternates COM1 and DebugCon output, with a delay loop between the two pairs.
+const INTERLEAVED_OUTPUT: [u8; 37] = [
+    0xFA, // cli
+    0xBA, 0xF8, 0x03, // mov dx, 0x3F8
+    0xB0, b'S', // mov al, 'S'
+    0xEE, // out dx, al
+    0xBA, 0xE9, 0x00, // mov dx, 0xE9
+    0xB0, b'd', // mov al, 'd'
+    0xEE, // out dx, al
+    0xB9, 0x00, 0x10, // mov cx, 0x1000
+    0xE2, 0xFE, // loop $
+    0xBA, 0xF8, 0x03, // mov dx, 0x3F8
+    0xB0, b'T', // mov al, 'T'
+    0xEE, // out dx, al
+    0xBA, 0xE9, 0x00, // mov dx, 0xE9
+    0xB0, b'e', // mov al, 'e'
+    0xEE, // out dx, al
+    0xB0, b'f', // mov al, 'f'
+    0xEE, // out dx, al
+    0xF4, // hlt
+    0xEB, 0xFD, // jmp hlt
+    0x90, // nop
+];
+
+/// Fails an INT 13h read on a missing drive, touches an unclaimed port, then resets via `0xCF9`.
+const FAILURES_THEN_RESET: [u8; 30] = [
+    0xFA, // cli
+    0x31, 0xC0, // xor ax, ax
+    0x8E, 0xC0, // mov es, ax
+    0xBB, 0x00, 0x80, // mov bx, 0x8000
+    0xB8, 0x01, 0x02, // mov ax, 0x0201 (read one sector)
+    0xB9, 0x01, 0x00, // mov cx, 0x0001
+    0xBA, 0x81, 0x00, // mov dx, 0x0081 (second hard disk: not present)
+    0xCD, 0x13, // int 0x13
+    0xBA, 0x34, 0x12, // mov dx, 0x1234
+    0xEC, // in al, dx
+    0xBA, 0xF9, 0x0C, // mov dx, 0xCF9
+    0xB0, 0x06, // mov al, 0x06
+    0xEE, // out dx, al
+    0xF4, // hlt
+];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: true,
+        enable_debugcon: true,
+        enable_reset_ctrl: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest never reached HLT");
+}
+
+fn kinds(events: &[MachineEvent]) -> Vec<MachineEventKind> {
+    events.iter().map(|event| event.kind.clone()).collect()
+}
+
+fn serial(bytes: &[u8]) -> MachineEventKind {
+    MachineEventKind::SerialOut {
+        port: 0,
+        bytes: bytes.to_vec(),
+    }
+}
+
+fn debugcon(bytes: &[u8]) -> MachineEventKind {
+    MachineEventKind::DebugCon {
+        bytes: bytes.to_vec(),
+    }
+}
+
+#[test]
+fn serial_and_debugcon_output_interleave_in_guest_order() {
+    let mut m = new_machine(&INTERLEAVED_OUTPUT);
+    m.set_event_mask(MachineEventMask::SERIAL_OUT | MachineEventMask::DEBUGCON);
+    run_until_halt(&mut m);
+
+    let events = m.take_events(usize::MAX);
+    assert_eq!(
+        kinds(&events),
+        vec![serial(b"S"), debugcon(b"d"), serial(b"T"), debugcon(b"ef")]
+    );
+    assert!(events.windows(2).all(|w| w[0].seq < w[1].seq));
+    assert!(events
+        .windows(2)
+        .all(|w| w[0].guest_time_ns <= w[1].guest_time_ns));
+    // The delay loop spans several batches, so guest time visibly advances across it.
+    assert!(events[2].guest_time_ns > events[1].guest_time_ns);
+
+    // The stream is recorded in addition to the existing per-subsystem logs.
+    assert_eq!(m.take_serial_output(), b"ST");
+    assert_eq!(m.take_debugcon_output(), b"def");
+    assert_eq!(m.pending_event_count(), 0);
+    assert_eq!(m.take_event_drops().total(), 0);
+}
+
+#[test]
+fn event_mask_filters_categories_and_defaults_to_none() {
+    let mut m = new_machine(&INTERLEAVED_OUTPUT);
+    assert_eq!(m.event_mask(), MachineEventMask::NONE);
+    run_until_halt(&mut m);
+    assert!(m.take_events(usize::MAX).is_empty());
+
+    let mut m = new_machine(&INTERLEAVED_OUTPUT);
+    m.set_event_mask(MachineEventMask::DEBUGCON);
+    run_until_halt(&mut m);
+    assert_eq!(
+        kinds(&m.take_events(usize::MAX)),
+        vec![debugcon(b"d"), debugcon(b"ef")]
+    );
+    assert_eq!(m.take_serial_output(), b"ST");
+}
+
+#[test]
+fn full_stream_drops_new_events_and_counts_them_per_category() {
+    let mut m = new_machine(&INTERLEAVED_OUTPUT);
+    m.set_event_mask(MachineEventMask::ALL);
+    m.set_event_capacity(2);
+    assert_eq!(m.event_capacity(), 2);
+    run_until_halt(&mut m);
+
+    // The oldest undrained events are kept; the drop counters say what was lost.
+    assert_eq!(
+        kinds(&m.take_events(1)),
+        vec![serial(b"S")],
+        "take_events honours max"
+    );
+    assert_eq!(kinds(&m.take_events(usize::MAX)), vec![debugcon(b"d")]);
+    let drops = m.take_event_drops();
+    assert_eq!(drops.get(MachineEventCategory::SerialOut), 1);
+    // Output is only coalesced into a retained event, so 'e' and 'f' are dropped separately.
+    assert_eq!(drops.get(MachineEventCategory::DebugCon), 2);
+    assert_eq!(drops.total(), 3);
+    assert_eq!(m.take_event_drops().total(), 0);
+
+    // Draining makes room again.
+    m.io_write(0xE9, 1, u32::from(b'x'));
+    assert_eq!(kinds(&m.take_events(usize::MAX)), vec![debugcon(b"x")]);
+}
+
+#[test]
+fn boot_storage_unknown_io_and_reset_events_share_one_timeline() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_reset_ctrl: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(&FAILURES_THEN_RESET)).unwrap();
+    m.set_event_mask(
+        MachineEventMask::BOOT_STAGE
+            | MachineEventMask::STORAGE_ERROR
+            | MachineEventMask::UNKNOWN_IO
+            | MachineEventMask::RESET_REQUESTED,
+    );
+    m.reset();
+    let post = m.take_events(usize::MAX);
+    assert_eq!(
+        kinds(&post),
+        vec![MachineEventKind::BootStage(BootStage::PostComplete)]
+    );
+
+    let exit = m.run_slice(100_000);
+    assert!(
+        matches!(exit, RunExit::ResetRequested { .. }),
+        "unexpected exit: {exit:?}"
+    );
+    let events = m.take_events(usize::MAX);
+    let kinds = kinds(&events);
+    assert_eq!(kinds.len(), 4, "{kinds:?}");
+    assert_eq!(kinds[0], MachineEventKind::BootStage(BootStage::Bootloader));
+    assert!(
+        matches!(
+            kinds[1],
+            MachineEventKind::StorageError {
+                drive: 0x81,
+                function: 0x02,
+                status,
+            } if status != 0
+        ),
+        "{:?}",
+        kinds[1]
+    );
+    assert_eq!(
+        kinds[2],
+        MachineEventKind::UnknownIo {
+            space: UnknownAccessSpace::PortIo,
+            addr: 0x1234,
+            size: 1,
+            write: false,
+        }
+    );
+    assert!(
+        matches!(
+            &kinds[3],
+            MachineEventKind::ResetRequested {
+                source: ResetSource::Port0xCF9,
+                ..
+            }
+        ),
+        "{:?}",
+        kinds[3]
+    );
+    assert!(post
+        .iter()
+        .chain(&events)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .all(|w| w[0].seq < w[1].seq));
+
+    // Polling the same pending reset again does not report it twice.
+    assert!(matches!(m.run_slice(1), RunExit::ResetRequested { .. }));
+    assert!(m.take_events(usize::MAX).is_empty());
+}
@@ -0,0 +1,189 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_storage::{MemBackend, RawDisk, SECTOR_SIZE};
+
+const FDC_DOR: u16 = 0x3F2;
+const FDC_MSR: u16 = 0x3F4;
+const FDC_FIFO: u16 = 0x3F5;
+const FLOPPY_SECTORS: usize = 2880;
+
+fn machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        // Keep this test minimal/deterministic.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn floppy_image() -> Box<RawDisk<MemBackend>> {
+    let mut bytes = vec![0u8; FLOPPY_SECTORS * SECTOR_SIZE];
+    for (lba, sector) in bytes.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+        sector.fill((lba % 251) as u8);
+    }
+    Box::new(RawDisk::open(MemBackend::from_vec(bytes)).unwrap())
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("machine did not halt within slice budget");
+}
+
+fn fdc_command(m: &mut Machine, bytes: &[u8]) {
+    for &b in bytes {
+        assert_eq!(
+            m.io_read(FDC_MSR, 1) & 0xC0,
+            0x80,
+            "FDC not ready for command byte"
+        );
+        m.io_write(FDC_FIFO, 1, u32::from(b));
+    }
+}
+
+fn fdc_results(m: &mut Machine) -> Vec<u8> {
+    let mut out = Vec::new();
+    while m.io_read(FDC_MSR, 1) & 0xC0 == 0xC0 {
+        out.push(m.io_read(FDC_FIFO, 1) as u8);
+    }
+    out
+}
+
+fn fdc_reset(m: &mut Machine) {
+    m.io_write(FDC_DOR, 1, 0x00);
+    m.io_write(FDC_DOR, 1, 0x1C);
+    for drive in 0..4u8 {
+        fdc_command(m, &[0x08]);
+        assert_eq!(fdc_results(m), vec![0xC0 | drive, 0]);
+    }
+}
+
+#[test]
+fn bios_int13_and_fdc_dma_read_the_same_floppy_image() {
+    let mut m = machine();
+
+    // HDD boot sector: INT 13h AH=02 read C=0 H=1 S=1 from drive 0x00 into 0000:1000, then HLT.
+    let mut boot = vec![0u8; SECTOR_SIZE];
+    let code = [
+        0xB8, 0x01, 0x02, // mov ax, 0x0201
+        0xB9, 0x01, 0x00, // mov cx, 0x0001
+        0xBA, 0x00, 0x01, // mov dx, 0x0100
+        0x31, 0xDB, // xor bx, bx
+        0x8E, 0xC3, // mov es, bx
+        0xBB, 0x00, 0x10, // mov bx, 0x1000
+        0xCD, 0x13, // int 0x13
+        0xF4, // hlt
+    ];
+    boot[..code.len()].copy_from_slice(&code);
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    m.set_disk_image(boot).unwrap();
+    m.attach_floppy_image(floppy_image()).unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+
+    // Drive A: is advertised in the BDA equipment word and CMOS.
+    assert_eq!(m.read_physical_u16(0x410) & 0xC1, 0x01);
+    m.io_write(0x70, 1, 0x10);
+    assert_eq!(m.io_read(0x71, 1), 0x40);
+
+    // C=0 H=1 S=1 is LBA 18.
+    assert_eq!(
+        m.read_physical_bytes(0x1000, SECTOR_SIZE),
+        vec![18u8; SECTOR_SIZE]
+    );
+
+    // Read the same sector through the FDC on ISA DMA channel 2 into 0x2000.
+    fdc_reset(&mut m);
+    m.io_write(0x0A, 1, 0x06);
+    m.io_write(0x0C, 1, 0);
+    m.io_write(0x0B, 1, 0x46);
+    m.io_write(0x04, 1, 0x00);
+    m.io_write(0x04, 1, 0x20);
+    m.io_write(0x81, 1, 0x00);
+    m.io_write(0x05, 1, 0xFF);
+    m.io_write(0x05, 1, 0x01);
+    m.io_write(0x0A, 1, 0x02);
+    fdc_command(&mut m, &[0x46, 0x04, 0, 1, 1, 2, 18, 0x1B, 0xFF]);
+    m.process_fdc();
+    assert_eq!(fdc_results(&mut m), vec![0x04, 0, 0, 0, 1, 2, 2]);
+    assert_eq!(
+        m.read_physical_bytes(0x2000, SECTOR_SIZE),
+        vec![18u8; SECTOR_SIZE]
+    );
+
+    // Ejecting the image makes FDC reads fail while the drive stays present.
+    m.eject_floppy();
+    m.io_write(0x05, 1, 0xFF);
+    m.io_write(0x05, 1, 0x01);
+    m.io_write(0x0A, 1, 0x02);
+    fdc_command(&mut m, &[0x46, 0x00, 0, 0, 1, 2, 18, 0x1B, 0xFF]);
+    m.process_fdc();
+    assert_eq!(fdc_results(&mut m)[0] & 0xC0, 0x40);
+}
+
+#[test]
+fn attach_floppy_image_rejects_non_144mb_images() {
+    let mut m = machine();
+    let disk = RawDisk::open(MemBackend::from_vec(vec![0u8; 720 * 1024])).unwrap();
+    assert!(m.attach_floppy_image(Box::new(disk)).is_err());
+}
+
+#[test]
+fn floppy_snapshot_keeps_fdc_state_and_records_overlay_ref() {
+    let mut m = machine();
+    m.attach_floppy_image(floppy_image()).unwrap();
+    m.set_floppy_overlay_ref("floppy.img", "floppy.overlay");
+    fdc_reset(&mut m);
+    fdc_command(&mut m, &[0x0F, 0x00, 7]);
+
+    let snapshot = m.take_snapshot_full().unwrap();
+
+    let mut restored = machine();
+    restored.attach_floppy_image(floppy_image()).unwrap();
+    restored.restore_snapshot_bytes(&snapshot).unwrap();
+
+    let overlays = restored.restored_disk_overlays().unwrap();
+    let floppy = overlays
+        .disks
+        .iter()
+        .find(|d| d.disk_id == Machine::DISK_ID_FLOPPY)
+        .expect("floppy overlay ref should be snapshotted");
+    assert_eq!(floppy.base_image, "floppy.img");
+    assert_eq!(floppy.overlay_image, "floppy.overlay");
+
+    // The pending seek interrupt survives restore.
+    fdc_command(&mut restored, &[0x08]);
+    assert_eq!(fdc_results(&mut restored), vec![0x20, 7]);
+}
+
+#[test]
+fn eject_floppy_drops_overlay_ref_from_snapshots() {
+    let mut m = machine();
+    m.attach_floppy_image(floppy_image()).unwrap();
+    m.set_floppy_overlay_ref("floppy.img", "floppy.overlay");
+    m.eject_floppy();
+
+    let snapshot = m.take_snapshot_full().unwrap();
+    let mut restored = machine();
+    restored.restore_snapshot_bytes(&snapshot).unwrap();
+
+    let overlays = restored.restored_disk_overlays().unwrap();
+    assert!(overlays
+        .disks
+        .iter()
+        .all(|d| d.disk_id != Machine::DISK_ID_FLOPPY));
+}
@@ -0,0 +1,102 @@
+use aero_machine::{Machine, MachineConfig, MemoryRegion, MemoryRegionKind};
+use aero_snapshot as snapshot;
+use firmware::bios::{EBDA_BASE, PCIE_ECAM_BASE};
+use pretty_assertions::assert_eq;
+
+const ONE_MIB: u64 = 0x10_0000;
+const FOUR_GIB: u64 = 0x1_0000_0000;
+
+fn assert_well_formed(map: &[MemoryRegion], ram_size_bytes: u64) {
+    for pair in map.windows(2) {
+        assert!(pair[0].end() <= pair[1].start, "overlap: {pair:?}");
+    }
+    // Every configured byte of RAM is described, minus the EBDA/VGA/ROM window below 1MiB.
+    let ram: u64 = map.iter().filter(|r| r.kind.is_ram()).map(|r| r.len).sum();
+    assert_eq!(ram, ram_size_bytes - (ONE_MIB - EBDA_BASE));
+}
+
+#[test]
+fn memory_map_includes_acpi_windows_from_post() {
+    let ram_size_bytes = 64 * 1024 * 1024;
+    let m = Machine::new(MachineConfig::win7_storage(ram_size_bytes)).unwrap();
+    let map = m.guest_memory_map();
+    assert_well_formed(&map, ram_size_bytes);
+
+    assert!(map
+        .iter()
+        .any(|r| r.kind == MemoryRegionKind::AcpiReclaim && r.len > 0));
+    assert!(map.iter().any(|r| r.kind == MemoryRegionKind::RomShadow));
+    assert!(!map.iter().any(|r| matches!(
+        r.kind,
+        MemoryRegionKind::PciHole | MemoryRegionKind::HighRamRemap
+    )));
+}
+
+#[test]
+fn ram_above_4gib_is_reachable_at_the_remapped_addresses() {
+    // 3.75GiB: 1GiB of RAM lands above 4GiB (sparse backing, so this does not allocate it).
+    let ram_size_bytes = 0xF000_0000;
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let map = m.guest_memory_map();
+    assert_well_formed(&map, ram_size_bytes);
+
+    let high: Vec<_> = map
+        .iter()
+        .filter(|r| r.kind == MemoryRegionKind::HighRamRemap)
+        .copied()
+        .collect();
+    assert_eq!(
+        high,
+        vec![MemoryRegion {
+            start: FOUR_GIB,
+            len: ram_size_bytes - PCIE_ECAM_BASE,
+            kind: MemoryRegionKind::HighRamRemap,
+        }]
+    );
+
+    // The first and last dword of every RAM region above 1MiB round-trips and does not alias any
+    // other region.
+    let ram_regions: Vec<_> = map
+        .iter()
+        .filter(|r| r.kind.is_ram() && r.start >= ONE_MIB)
+        .copied()
+        .collect();
+    let probes: Vec<u64> = ram_regions
+        .iter()
+        .flat_map(|r| [r.start, r.end() - 4])
+        .collect();
+    for (i, &addr) in probes.iter().enumerate() {
+        m.write_physical_u32(addr, 0x5A00_0000 | i as u32);
+    }
+    for (i, &addr) in probes.iter().enumerate() {
+        assert_eq!(
+            m.read_physical_u32(addr),
+            0x5A00_0000 | i as u32,
+            "RAM at {addr:#x} is not reachable"
+        );
+    }
+
+    // Remapped RAM is the tail of the contiguous RAM image.
+    let mut buf = [0u8; 4];
+    snapshot::SnapshotSource::read_ram(&m, ram_size_bytes - 4, &mut buf).unwrap();
+    assert_eq!(
+        u32::from_le_bytes(buf),
+        m.read_physical_u32(high[0].end() - 4)
+    );
+
+    // Nothing in the PCI hole is RAM.
+    for region in map
+        .iter()
+        .filter(|r| r.kind == MemoryRegionKind::PciHole || r.start == PCIE_ECAM_BASE)
+    {
+        m.write_physical_u8(region.start, 0);
+        assert_eq!(m.read_physical_u8(region.start), 0xFF, "{region:?}");
+    }
+}
@@ -0,0 +1,264 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::i8042::{I8042_DATA_PORT, I8042_STATUS_PORT};
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{Machine, MachineConfig};
+use aero_virtio::devices::input::{ABS_X, ABS_Y, EV_ABS, EV_SYN, SYN_REPORT};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use aero_virtio::queue::VIRTQ_DESC_F_WRITE;
+use pretty_assertions::assert_eq;
+
+const TYPE_KEY_SCANCODE: u32 = 1;
+const TYPE_KEY_HID_USAGE: u32 = 6;
+const TYPE_MOUSE_ABSOLUTE: u32 = 8;
+
+fn batch(events: &[[u32; 4]]) -> Vec<u32> {
+    let mut words = vec![events.len() as u32, 0];
+    for ev in events {
+        words.extend_from_slice(ev);
+    }
+    words
+}
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+fn drain_i8042_output(m: &mut Machine) -> Vec<u8> {
+    let mut out = Vec::new();
+    for _ in 0..64 {
+        let status = m.io_read(I8042_STATUS_PORT, 1) as u8;
+        if (status & 0x01) == 0 {
+            break;
+        }
+        out.push(m.io_read(I8042_DATA_PORT, 1) as u8);
+    }
+    out
+}
+
+const EVENTQ_DESC: u64 = 0x10000;
+const EVENTQ_AVAIL: u64 = 0x11000;
+const EVENTQ_USED: u64 = 0x12000;
+const EVENT_BUFS: u64 = 0x13000;
+const EVENT_BUF_COUNT: u16 = 12;
+
+/// Bring the virtio-input tablet to DRIVER_OK and post `EVENT_BUF_COUNT` 8-byte event buffers.
+fn setup_tablet_eventq(m: &mut Machine) {
+    let bdf = profile::VIRTIO_INPUT_TABLET.bdf;
+    let bar0_lo = cfg_read(m, bdf, 0x10, 4);
+    let bar0_hi = cfg_read(m, bdf, 0x14, 4);
+    let common = (u64::from(bar0_hi) << 32) | u64::from(bar0_lo & 0xFFFF_FFF0);
+    assert_ne!(common, 0, "virtio-input BAR0 must be assigned by BIOS POST");
+    let notify = common + 0x1000;
+
+    let cmd = cfg_read(m, bdf, 0x04, 2) | 0x0006; // MEM + BUSMASTER
+    cfg_write(m, bdf, 0x04, 2, cmd);
+
+    let mut status = VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER;
+    m.write_physical_u8(common + 0x14, status);
+    for sel in 0..2 {
+        m.write_physical_u32(common, sel);
+        let features = m.read_physical_u32(common + 0x04);
+        m.write_physical_u32(common + 0x08, sel);
+        m.write_physical_u32(common + 0x0c, features);
+    }
+    status |= VIRTIO_STATUS_FEATURES_OK;
+    m.write_physical_u8(common + 0x14, status);
+    m.write_physical_u8(common + 0x14, status | VIRTIO_STATUS_DRIVER_OK);
+    assert!(m.virtio_input_tablet_driver_ok());
+
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, EVENTQ_DESC);
+    m.write_physical_u64(common + 0x28, EVENTQ_AVAIL);
+    m.write_physical_u64(common + 0x30, EVENTQ_USED);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+
+    for i in 0..EVENT_BUF_COUNT {
+        let desc = EVENTQ_DESC + u64::from(i) * 16;
+        m.write_physical_u64(desc, EVENT_BUFS + u64::from(i) * 8);
+        m.write_physical_u32(desc + 8, 8);
+        m.write_physical_u16(desc + 12, VIRTQ_DESC_F_WRITE);
+        m.write_physical_u16(desc + 14, 0);
+        m.write_physical_u16(EVENTQ_AVAIL + 4 + u64::from(i) * 2, i);
+    }
+    m.write_physical_u16(EVENTQ_AVAIL, 0);
+    m.write_physical_u16(EVENTQ_AVAIL + 2, EVENT_BUF_COUNT);
+    m.write_physical_u16(EVENTQ_USED, 0);
+    m.write_physical_u16(EVENTQ_USED + 2, 0);
+
+    m.write_physical_u16(notify, 0);
+    m.process_virtio_input();
+}
+
+/// `(type, code, value)` of every event the tablet has completed so far.
+fn delivered_events(m: &mut Machine) -> Vec<(u16, u16, i32)> {
+    let used = m.read_physical_u16(EVENTQ_USED + 2);
+    (0..u64::from(used))
+        .map(|i| {
+            let b = m.read_physical_bytes(EVENT_BUFS + i * 8, 8);
+            (
+                u16::from_le_bytes([b[0], b[1]]),
+                u16::from_le_bytes([b[2], b[3]]),
+                i32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+            )
+        })
+        .collect()
+}
+
+fn abs_frame(x: i32, y: i32) -> [(u16, u16, i32); 3] {
+    [
+        (EV_ABS, ABS_X, x),
+        (EV_ABS, ABS_Y, y),
+        (EV_SYN, SYN_REPORT, 0),
+    ]
+}
+
+#[test]
+fn mouse_absolute_scales_focused_head_pixels_into_tablet_range() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: true,
+        enable_vga: false,
+        enable_virtio_input: true,
+        enable_virtio_input_tablet: true,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    setup_tablet_eventq(&mut m);
+
+    // No frame presented yet: head 0 has no resolution, so absolute events are dropped.
+    m.inject_input_batch(&batch(&[[TYPE_MOUSE_ABSOLUTE, 0, 10 | (10 << 16), 0]]));
+    assert_eq!(delivered_events(&mut m), vec![]);
+
+    // Program a 200x100x8bpp VBE mode via Bochs VBE_DISPI ports and present it.
+    for (index, value) in [(1, 200), (2, 100), (3, 8), (4, 0x0041)] {
+        m.io_write(0x01CE, 2, index);
+        m.io_write(0x01CF, 2, value);
+    }
+    m.display_present(true);
+    assert_eq!(m.display_resolution(), (200, 100));
+    assert_eq!(m.input_focus(), 0);
+
+    m.inject_input_batch(&batch(&[
+        // Bottom-right pixel of the focused head.
+        [TYPE_MOUSE_ABSOLUTE, 0, 199 | (99 << 16), 0],
+        // Explicit head 0; positions past the edge clamp to the last pixel.
+        [TYPE_MOUSE_ABSOLUTE, 0, 99 | (500 << 16), 0x100],
+        // Head 1 is not scanning out.
+        [TYPE_MOUSE_ABSOLUTE, 0, 5 | (5 << 16), 0x101],
+    ]));
+    let mut expected = Vec::new();
+    expected.extend(abs_frame(32767, 32767));
+    expected.extend(abs_frame(99 * 32767 / 199, 32767));
+    assert_eq!(delivered_events(&mut m), expected);
+
+    // Focus on head 1: unaddressed events follow the focus and are dropped, explicit head 0 still
+    // maps through head 0.
+    m.set_input_focus(1);
+    m.inject_input_batch(&batch(&[
+        [TYPE_MOUSE_ABSOLUTE, 0, 0, 0],
+        [TYPE_MOUSE_ABSOLUTE, 0, 0, 0x100],
+    ]));
+    expected.extend(abs_frame(0, 0));
+    assert_eq!(delivered_events(&mut m), expected);
+
+    // Focus is host policy and survives reset.
+    m.reset();
+    assert_eq!(m.input_focus(), 1);
+}
+
+#[test]
+fn keyboard_grab_off_filters_gui_key_presses_but_not_releases() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_i8042: true,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        enable_virtio_input: false,
+        enable_uhci: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let _ = drain_i8042_output(&mut m);
+    assert!(m.keyboard_grab());
+    assert_eq!(m.keyboard_grab_filter(), &[0xE3, 0xE7]);
+
+    let meta_left_make = [
+        [TYPE_KEY_HID_USAGE, 0, 0x1E3, 0],
+        [TYPE_KEY_SCANCODE, 0, 0x1FE0, 2],
+    ];
+    let meta_left_break = [
+        [TYPE_KEY_HID_USAGE, 0, 0x0E3, 0],
+        [TYPE_KEY_SCANCODE, 0, 0x1F_F0E0, 3],
+    ];
+
+    m.set_keyboard_grab(false);
+    m.inject_input_batch(&batch(&meta_left_make));
+    assert_eq!(drain_i8042_output(&mut m), Vec::<u8>::new());
+
+    // Unfiltered keys still pass ('A', Set-2 0x1C; translated to Set-1 0x1E by default).
+    m.inject_input_batch(&batch(&[
+        [TYPE_KEY_HID_USAGE, 0, 0x104, 0],
+        [TYPE_KEY_SCANCODE, 0, 0x1C, 1],
+    ]));
+    let out = drain_i8042_output(&mut m);
+    assert!(out == vec![0x1E] || out == vec![0x1C], "got {out:02x?}");
+
+    // Releases always pass so a key pressed while grabbed cannot get stuck.
+    m.inject_input_batch(&batch(&meta_left_break));
+    let out = drain_i8042_output(&mut m);
+    assert!(
+        out == vec![0xE0, 0xDB] || out == vec![0xE0, 0xF0, 0x1F],
+        "got {out:02x?}"
+    );
+
+    m.set_keyboard_grab(true);
+    m.inject_input_batch(&batch(&meta_left_make));
+    let out = drain_i8042_output(&mut m);
+    assert!(
+        out == vec![0xE0, 0x5B] || out == vec![0xE0, 0x1F],
+        "got {out:02x?}"
+    );
+
+    // The filter is configurable: with an empty filter nothing is dropped even when ungrabbed.
+    m.set_keyboard_grab(false);
+    m.set_keyboard_grab_filter(&[]);
+    m.inject_input_batch(&batch(&meta_left_break));
+    let _ = drain_i8042_output(&mut m);
+    m.inject_input_batch(&batch(&meta_left_make));
+    assert!(!drain_i8042_output(&mut m).is_empty());
+
+    // Grab state is host policy and survives reset.
+    m.reset();
+    assert!(!m.keyboard_grab());
+    assert_eq!(m.keyboard_grab_filter(), &[] as &[u8]);
+}
@@ -0,0 +1,241 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{InputBackend, InputLatencySample, Machine, MachineConfig, RunExit};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use aero_virtio::queue::VIRTQ_DESC_F_WRITE;
+use pretty_assertions::assert_eq;
+
+const TYPE_KEY_SCANCODE: u32 = 1;
+const TYPE_KEY_HID_USAGE: u32 = 6;
+
+fn batch(events: &[[u32; 4]]) -> Vec<u32> {
+    let mut words = vec![events.len() as u32, 0];
+    for ev in events {
+        words.extend_from_slice(ev);
+    }
+    words
+}
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+/// A guest that polls the i8042 with interrupts disabled and reads every byte from port 0x60.
+fn build_polling_boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    let code: &[&[u8]] = &[
+        &[0xFA],       // cli
+        &[0xE4, 0x64], // .poll: in al, 0x64
+        &[0xA8, 0x01], // test al, 1
+        &[0x74, 0xFA], // jz .poll
+        &[0xE4, 0x60], // in al, 0x60
+        &[0xEB, 0xF6], // jmp .poll
+    ];
+    let code = code.concat();
+    sector[..code.len()].copy_from_slice(&code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn polling_machine(enable_virtio_input: bool) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_i8042: true,
+        enable_serial: false,
+        enable_vga: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        enable_virtio_input,
+        enable_uhci: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(build_polling_boot_sector().to_vec())
+        .unwrap();
+    m.reset();
+    run(&mut m);
+    m
+}
+
+fn run(m: &mut Machine) {
+    for _ in 0..4 {
+        match m.run_slice(10_000) {
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+}
+
+const EVENTQ_DESC: u64 = 0x10000;
+const EVENTQ_AVAIL: u64 = 0x11000;
+const EVENTQ_USED: u64 = 0x12000;
+const EVENT_BUFS: u64 = 0x13000;
+
+/// Bring the virtio-input keyboard to DRIVER_OK with an event queue that has no buffers yet.
+/// Returns the queue notify address.
+fn setup_keyboard_eventq(m: &mut Machine) -> u64 {
+    let bdf = profile::VIRTIO_INPUT_KEYBOARD.bdf;
+    let bar0_lo = cfg_read(m, bdf, 0x10, 4);
+    let bar0_hi = cfg_read(m, bdf, 0x14, 4);
+    let common = (u64::from(bar0_hi) << 32) | u64::from(bar0_lo & 0xFFFF_FFF0);
+    assert_ne!(common, 0, "virtio-input BAR0 must be assigned by BIOS POST");
+
+    let cmd = cfg_read(m, bdf, 0x04, 2) | 0x0006; // MEM + BUSMASTER
+    cfg_write(m, bdf, 0x04, 2, cmd);
+
+    let mut status = VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER;
+    m.write_physical_u8(common + 0x14, status);
+    for sel in 0..2 {
+        m.write_physical_u32(common, sel);
+        let features = m.read_physical_u32(common + 0x04);
+        m.write_physical_u32(common + 0x08, sel);
+        m.write_physical_u32(common + 0x0c, features);
+    }
+    status |= VIRTIO_STATUS_FEATURES_OK;
+    m.write_physical_u8(common + 0x14, status);
+    m.write_physical_u8(common + 0x14, status | VIRTIO_STATUS_DRIVER_OK);
+    assert!(m.virtio_input_keyboard_driver_ok());
+
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, EVENTQ_DESC);
+    m.write_physical_u64(common + 0x28, EVENTQ_AVAIL);
+    m.write_physical_u64(common + 0x30, EVENTQ_USED);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+    m.write_physical_u16(EVENTQ_AVAIL, 0);
+    m.write_physical_u16(EVENTQ_AVAIL + 2, 0);
+    m.write_physical_u16(EVENTQ_USED, 0);
+    m.write_physical_u16(EVENTQ_USED + 2, 0);
+
+    common + 0x1000
+}
+
+/// Post `count` 8-byte event buffers and kick the queue.
+fn post_event_buffers(m: &mut Machine, notify: u64, count: u16) {
+    for i in 0..count {
+        let desc = EVENTQ_DESC + u64::from(i) * 16;
+        m.write_physical_u64(desc, EVENT_BUFS + u64::from(i) * 8);
+        m.write_physical_u32(desc + 8, 8);
+        m.write_physical_u16(desc + 12, VIRTQ_DESC_F_WRITE);
+        m.write_physical_u16(desc + 14, 0);
+        m.write_physical_u16(EVENTQ_AVAIL + 4 + u64::from(i) * 2, i);
+    }
+    m.write_physical_u16(EVENTQ_AVAIL + 2, count);
+    m.write_physical_u16(notify, 0);
+}
+
+fn assert_ordered(samples: &[InputLatencySample]) {
+    for pair in samples.windows(2) {
+        assert!(pair[0].inject_guest_ns <= pair[1].inject_guest_ns);
+    }
+    for sample in samples {
+        let consume = sample.consume_guest_ns.expect("consume time recorded");
+        assert!(
+            consume > sample.inject_guest_ns,
+            "consumed before delivery: {sample:?}"
+        );
+    }
+}
+
+#[test]
+fn sampling_is_off_by_default() {
+    let mut m = polling_machine(false);
+    m.inject_input_batch(&batch(&[[TYPE_KEY_SCANCODE, 100, 0x1C, 1]]));
+    run(&mut m);
+    assert_eq!(m.take_input_latency_samples(), Vec::new());
+}
+
+#[test]
+fn ps2_samples_are_completed_by_port_0x60_reads() {
+    let mut m = polling_machine(false);
+    m.start_input_latency_sampling();
+
+    // 'A' make and break (Set-2), and an event without a host timestamp.
+    m.inject_input_batch(&batch(&[
+        [TYPE_KEY_SCANCODE, 1_000, 0x1C, 1],
+        [TYPE_KEY_SCANCODE, 2_000, 0x1C_F0, 2],
+    ]));
+    m.inject_input_batch(&batch(&[[TYPE_KEY_SCANCODE, 0, 0x1C, 1]]));
+    // Nothing is complete until the guest has read the bytes.
+    assert_eq!(m.take_input_latency_samples(), Vec::new());
+
+    run(&mut m);
+    let samples = m.take_input_latency_samples();
+    assert_eq!(
+        samples
+            .iter()
+            .map(|s| (s.host_ts, s.backend))
+            .collect::<Vec<_>>(),
+        vec![
+            (Some(1_000), InputBackend::Ps2Keyboard),
+            (Some(2_000), InputBackend::Ps2Keyboard),
+            (None, InputBackend::Ps2Keyboard),
+        ]
+    );
+    assert_ordered(&samples);
+    assert!(samples[1].consume_guest_ns <= samples[2].consume_guest_ns);
+    assert_eq!(m.take_input_latency_samples(), Vec::new());
+}
+
+#[test]
+fn virtio_samples_are_completed_when_the_event_is_used() {
+    let mut m = polling_machine(true);
+    let notify = setup_keyboard_eventq(&mut m);
+    m.start_input_latency_sampling();
+
+    // 'A' press: the virtio keyboard queues EV_KEY + SYN_REPORT, but the guest has posted no
+    // buffers yet.
+    m.inject_input_batch(&batch(&[[TYPE_KEY_HID_USAGE, 42, 0x104, 0]]));
+    run(&mut m);
+    assert_eq!(m.take_input_latency_samples(), Vec::new());
+
+    post_event_buffers(&mut m, notify, 2);
+    m.process_virtio_input();
+    assert_eq!(m.read_physical_u16(EVENTQ_USED + 2), 2);
+
+    let samples = m.take_input_latency_samples();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].host_ts, Some(42));
+    assert_eq!(samples[0].backend, InputBackend::VirtioKeyboard);
+    assert_ordered(&samples);
+}
+
+#[test]
+fn reset_completes_pending_samples_without_consume_time() {
+    let mut m = polling_machine(false);
+    m.start_input_latency_sampling();
+    m.inject_input_batch(&batch(&[[TYPE_KEY_SCANCODE, 7, 0x1C, 1]]));
+    m.reset();
+
+    let samples = m.take_input_latency_samples();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].host_ts, Some(7));
+    assert_eq!(samples[0].consume_guest_ns, None);
+
+    // Sampling stays enabled across reset.
+    run(&mut m);
+    m.inject_input_batch(&batch(&[[TYPE_KEY_SCANCODE, 8, 0x1C, 1]]));
+    run(&mut m);
+    let samples = m.take_input_latency_samples();
+    assert_eq!(samples.len(), 1);
+    assert_ordered(&samples);
+}
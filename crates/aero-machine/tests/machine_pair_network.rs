@@ -0,0 +1,203 @@
+use aero_devices::pci::{PciBdf, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig, MachinePair, PairSide};
+use pretty_assertions::assert_eq;
+
+const MAC_A: [u8; 6] = [0x52, 0x54, 0x00, 0x00, 0x00, 0x0a];
+const MAC_B: [u8; 6] = [0x52, 0x54, 0x00, 0x00, 0x00, 0x0b];
+const IP_A: [u8; 4] = [10, 0, 2, 10];
+const IP_B: [u8; 4] = [10, 0, 2, 11];
+
+/// Minimum Ethernet frame length without FCS; shorter frames are zero-padded.
+const ETH_MIN_FRAME_LEN: usize = 60;
+
+const TX_DESC_BASE: u64 = 0x20_000;
+const TX_BUF: u64 = 0x21_000;
+const RX_DESC_BASE: u64 = 0x22_000;
+const RX_BUF: u64 = 0x23_000;
+const TX_DESCS: u32 = 8;
+const RX_DESCS: u32 = 8;
+
+fn cfg(mac: [u8; 6]) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_e1000: true,
+        e1000_mac_addr: Some(mac),
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn cfg_addr(bdf: PciBdf, offset: u8) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device) << 11)
+        | (u32::from(bdf.function) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+/// Minimal "guest driver": enable bus mastering and program an E1000's TX/RX rings.
+///
+/// Returns BAR0. RX descriptor `i` points at `RX_BUF + i * 0x800`; all but one are handed to the
+/// NIC.
+fn init_e1000(m: &mut Machine) -> u64 {
+    let bdf = aero_devices::pci::profile::NIC_E1000_82540EM.bdf;
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, 0x10));
+    let bar0 = u64::from(m.io_read(PCI_CFG_DATA_PORT, 4) & 0xFFFF_FFF0);
+    assert_ne!(bar0, 0, "expected BAR0 to be assigned by BIOS POST");
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, 0x04));
+    let cmd = m.io_read(PCI_CFG_DATA_PORT, 2);
+    m.io_write(PCI_CFG_DATA_PORT, 2, cmd | 0x6); // MEM + BUSMASTER
+
+    m.write_physical_u32(bar0 + 0x3800, TX_DESC_BASE as u32); // TDBAL
+    m.write_physical_u32(bar0 + 0x3804, 0); // TDBAH
+    m.write_physical_u32(bar0 + 0x3808, 16 * TX_DESCS); // TDLEN
+    m.write_physical_u32(bar0 + 0x3810, 0); // TDH
+    m.write_physical_u32(bar0 + 0x3818, 0); // TDT
+    m.write_physical_u32(bar0 + 0x0400, 1 << 1); // TCTL.EN
+
+    for i in 0..u64::from(RX_DESCS) {
+        let mut desc = [0u8; 16];
+        desc[0..8].copy_from_slice(&(RX_BUF + i * 0x800).to_le_bytes());
+        m.write_physical(RX_DESC_BASE + i * 16, &desc);
+    }
+    m.write_physical_u32(bar0 + 0x2800, RX_DESC_BASE as u32); // RDBAL
+    m.write_physical_u32(bar0 + 0x2804, 0); // RDBAH
+    m.write_physical_u32(bar0 + 0x2808, 16 * RX_DESCS); // RDLEN
+    m.write_physical_u32(bar0 + 0x2810, 0); // RDH
+    m.write_physical_u32(bar0 + 0x2818, RX_DESCS - 1); // RDT
+    m.write_physical_u32(bar0 + 0x0100, 1 << 1); // RCTL.EN (2048-byte buffers)
+    bar0
+}
+
+/// Queue `frame` in TX descriptor `index` and advance TDT past it.
+fn transmit(m: &mut Machine, bar0: u64, index: u32, frame: &[u8]) {
+    let buf = TX_BUF + u64::from(index) * 0x800;
+    m.write_physical(buf, frame);
+    let mut desc = [0u8; 16];
+    desc[0..8].copy_from_slice(&buf.to_le_bytes());
+    desc[8..10].copy_from_slice(&(frame.len() as u16).to_le_bytes());
+    desc[11] = 0x01 | 0x08; // EOP | RS
+    m.write_physical(TX_DESC_BASE + u64::from(index) * 16, &desc);
+    m.write_physical_u32(bar0 + 0x3818, (index + 1) % TX_DESCS);
+}
+
+/// Completed frame in RX descriptor `index`, if the NIC has written one back.
+fn received(m: &mut Machine, index: u32) -> Option<Vec<u8>> {
+    let desc = m.read_physical_bytes(RX_DESC_BASE + u64::from(index) * 16, 16);
+    if desc[12] & 0x03 != 0x03 {
+        return None;
+    }
+    let len = usize::from(u16::from_le_bytes([desc[8], desc[9]]));
+    Some(m.read_physical_bytes(RX_BUF + u64::from(index) * 0x800, len))
+}
+
+fn arp_frame(
+    op: u16,
+    dst_mac: [u8; 6],
+    src_mac: [u8; 6],
+    sender_ip: [u8; 4],
+    target_mac: [u8; 6],
+    target_ip: [u8; 4],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ETH_MIN_FRAME_LEN);
+    frame.extend_from_slice(&dst_mac);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&0x0806u16.to_be_bytes()); // EtherType: ARP
+    frame.extend_from_slice(&1u16.to_be_bytes()); // HTYPE: Ethernet
+    frame.extend_from_slice(&0x0800u16.to_be_bytes()); // PTYPE: IPv4
+    frame.extend_from_slice(&[6, 4]); // HLEN, PLEN
+    frame.extend_from_slice(&op.to_be_bytes());
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&sender_ip);
+    frame.extend_from_slice(&target_mac);
+    frame.extend_from_slice(&target_ip);
+    frame.resize(ETH_MIN_FRAME_LEN, 0);
+    frame
+}
+
+/// Answer an ARP request for `my_ip`, the way a guest network stack would.
+fn arp_reply_for(request: &[u8], my_mac: [u8; 6], my_ip: [u8; 4]) -> Option<Vec<u8>> {
+    if request[12..14] != [0x08, 0x06] || request[20..22] != [0, 1] || request[38..42] != my_ip {
+        return None;
+    }
+    let requester_mac: [u8; 6] = request[22..28].try_into().unwrap();
+    let requester_ip: [u8; 4] = request[28..32].try_into().unwrap();
+    Some(arp_frame(
+        2,
+        requester_mac,
+        my_mac,
+        my_ip,
+        requester_mac,
+        requester_ip,
+    ))
+}
+
+#[test]
+fn machine_pair_exchanges_arp_request_and_reply() {
+    let mut pair = MachinePair::new(cfg(MAC_A), cfg(MAC_B)).unwrap();
+    let bar0_a = init_e1000(pair.a());
+    let bar0_b = init_e1000(pair.b());
+
+    // A asks who has B's address.
+    let request = arp_frame(1, [0xff; 6], MAC_A, IP_A, [0; 6], IP_B);
+    transmit(pair.a(), bar0_a, 0, &request);
+    pair.run_both(10_000, 10_000);
+
+    // Running the machines moves the frame onto the link, but never across it.
+    assert_eq!(pair.frames_in_flight(PairSide::A), 1);
+    assert_eq!(received(pair.b(), 0), None);
+
+    assert_eq!(pair.pump_network(), 1);
+    let got = received(pair.b(), 0).expect("B should have received the ARP request");
+    assert_eq!(got, request);
+
+    // B answers.
+    let reply = arp_reply_for(&got, MAC_B, IP_B).expect("request is for B");
+    transmit(pair.b(), bar0_b, 0, &reply);
+    pair.run_both(10_000, 10_000);
+    assert_eq!(received(pair.a(), 0), None);
+
+    assert_eq!(pair.pump_network(), 1);
+    let got = received(pair.a(), 0).expect("A should have received the ARP reply");
+    assert_eq!(got, reply);
+    assert_eq!(&got[0..6], &MAC_A);
+    assert_eq!(&got[22..28], &MAC_B);
+    assert_eq!(&got[28..32], &IP_B);
+
+    for side in [PairSide::A, PairSide::B] {
+        let stats = pair.link_stats(side);
+        assert_eq!(stats.transmitted, 1);
+        assert_eq!(stats.delivered, 1);
+        assert_eq!(stats.dropped_tx_full + stats.dropped_rx_full, 0);
+    }
+}
+
+#[test]
+fn machine_pair_link_is_bounded_and_counts_drops() {
+    let mut pair = MachinePair::with_queue_capacity(cfg(MAC_A), cfg(MAC_B), 2).unwrap();
+    let bar0_a = init_e1000(pair.a());
+    init_e1000(pair.b());
+
+    for i in 0..4u32 {
+        let frame = arp_frame(1, [0xff; 6], MAC_A, IP_A, [0; 6], [10, 0, 2, 100 + i as u8]);
+        transmit(pair.a(), bar0_a, i, &frame);
+    }
+    assert_eq!(pair.pump_network(), 2);
+
+    let stats = pair.link_stats(PairSide::A);
+    assert_eq!(stats.transmitted, 2);
+    assert_eq!(stats.dropped_tx_full, 2);
+    assert_eq!(stats.delivered, 2);
+
+    // The first two frames made it, in order.
+    for i in 0..2u32 {
+        let got = received(pair.b(), i).expect("frame should have been delivered");
+        assert_eq!(got[38..42], [10, 0, 2, 100 + i as u8]);
+    }
+    assert_eq!(received(pair.b(), 2), None);
+    assert_eq!(pair.link_stats(PairSide::B), Default::default());
+}
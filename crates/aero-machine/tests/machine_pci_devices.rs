@@ -0,0 +1,135 @@
+use aero_devices::pci::config::PciBarKind;
+use aero_devices::pci::msi::PCI_CAP_ID_MSI;
+use aero_devices::pci::profile::{AEROGPU, NIC_E1000_82540EM, SATA_AHCI_ICH9};
+use aero_devices::pci::PciBdf;
+use aero_machine::{Machine, MachineConfig, PciDeviceInfo};
+use pretty_assertions::assert_eq;
+
+fn browser_machine() -> Machine {
+    Machine::new(MachineConfig::browser_defaults(64 * 1024 * 1024)).unwrap()
+}
+
+fn info(m: &Machine, bdf: PciBdf) -> PciDeviceInfo {
+    m.pci_devices()
+        .into_iter()
+        .find(|dev| dev.bdf == bdf)
+        .unwrap_or_else(|| panic!("{bdf:?} missing from pci_devices()"))
+}
+
+#[test]
+fn pci_devices_reports_identity_and_assigned_bars() {
+    let m = browser_machine();
+    let devices = m.pci_devices();
+    for profile in [AEROGPU, NIC_E1000_82540EM, SATA_AHCI_ICH9] {
+        let dev = devices
+            .iter()
+            .find(|dev| dev.bdf == profile.bdf)
+            .unwrap_or_else(|| panic!("{} missing", profile.name));
+        assert_eq!(dev.vendor_id, profile.vendor_id);
+        assert_eq!(dev.device_id, profile.device_id);
+        assert_eq!(dev.subsystem_vendor_id, profile.subsystem_vendor_id);
+        assert_eq!(dev.subsystem_id, profile.subsystem_id);
+        assert_eq!(
+            (dev.class, dev.subclass, dev.prog_if),
+            (
+                profile.class.base_class,
+                profile.class.sub_class,
+                profile.class.prog_if
+            )
+        );
+        assert_eq!(dev.header_type & 0x7F, 0);
+        for bar in &dev.bars {
+            assert_eq!(m.pci_bar_base(dev.bdf, bar.index), Some(bar.base));
+        }
+    }
+
+    let e1000 = info(&m, NIC_E1000_82540EM.bdf);
+    let kinds: Vec<_> = e1000
+        .bars
+        .iter()
+        .map(|bar| (bar.index, bar.kind, bar.size))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![(0, PciBarKind::Mmio32, 0x20000), (1, PciBarKind::Io, 0x40)]
+    );
+    assert_eq!(e1000.interrupt_pin, 1);
+}
+
+#[test]
+fn pci_devices_reflects_guest_reprogramming() {
+    let mut m = browser_machine();
+    let bdf = NIC_E1000_82540EM.bdf;
+
+    // Decode disabled: BARs are reported but not enabled.
+    let command = m.pci_read_config(bdf, 0x04, 2).unwrap();
+    m.pci_write_config(bdf, 0x04, 2, command & !0x3);
+    let before = info(&m, bdf);
+    assert_eq!(before.command & 0x3, 0);
+    assert!(before.bars.iter().all(|bar| !bar.enabled));
+
+    // Relocate BAR0 and enable memory decode only.
+    let new_base = before.bars[0].base + 0x20000;
+    m.pci_write_config(bdf, 0x10, 4, new_base as u32);
+    m.pci_write_config(bdf, 0x04, 2, (command & !0x3) | 0x2);
+    m.pci_write_config(bdf, 0x3C, 1, 0x0B);
+
+    let after = info(&m, bdf);
+    assert_eq!(after.bars[0].base, new_base);
+    assert!(after.bars[0].enabled);
+    assert!(!after.bars[1].enabled, "I/O decode is still disabled");
+    assert_eq!(after.interrupt_line, 0x0B);
+    assert_eq!(m.pci_read_config(bdf, 0x3C, 1), Some(0x0B));
+}
+
+#[test]
+fn pci_devices_reports_msi_state() {
+    let mut m = browser_machine();
+    let bdf = SATA_AHCI_ICH9.bdf;
+
+    let ahci = info(&m, bdf);
+    assert!(ahci.capabilities.contains(&PCI_CAP_ID_MSI));
+    assert!(ahci.msi_present);
+    assert!(!ahci.msi_enabled);
+    assert!(!ahci.msix_present);
+
+    // Locate the MSI capability by walking the list through the debug accessors.
+    let mut cap = m.pci_read_config(bdf, 0x34, 1).unwrap() as u16;
+    while m.pci_read_config(bdf, cap, 1).unwrap() as u8 != PCI_CAP_ID_MSI {
+        cap = m.pci_read_config(bdf, cap + 1, 1).unwrap() as u16;
+        assert_ne!(cap, 0, "MSI capability not found");
+    }
+    let ctrl = m.pci_read_config(bdf, cap + 2, 2).unwrap();
+    m.pci_write_config(bdf, cap + 2, 2, ctrl | 1);
+    assert!(info(&m, bdf).msi_enabled);
+}
+
+#[test]
+fn config_accessors_follow_bus_semantics() {
+    let mut m = browser_machine();
+    let absent = PciBdf::new(0, 31, 7);
+    assert_eq!(m.pci_read_config(absent, 0, 4), Some(0xFFFF_FFFF));
+    assert_eq!(m.pci_read_config(absent, 0, 2), Some(0xFFFF));
+    m.pci_write_config(absent, 0x04, 2, 0x7);
+    assert!(m.pci_devices().iter().all(|dev| dev.bdf != absent));
+
+    let mut no_pci = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(no_pci.pci_devices(), vec![]);
+    assert_eq!(no_pci.pci_read_config(AEROGPU.bdf, 0, 4), None);
+    no_pci.pci_write_config(AEROGPU.bdf, 0x04, 2, 0x7);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn pci_device_info_serializes_to_json() {
+    let m = browser_machine();
+    let json = serde_json::to_value(info(&m, NIC_E1000_82540EM.bdf)).unwrap();
+    assert_eq!(json["bdf"], "00:05.0");
+    assert_eq!(json["vendor_id"], 0x8086);
+    assert_eq!(json["bars"][0]["kind"], "mmio32");
+    assert_eq!(json["bars"][1]["kind"], "io");
+}
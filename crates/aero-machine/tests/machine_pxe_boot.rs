@@ -0,0 +1,120 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_net_backend::NetworkBackend;
+use aero_storage::SECTOR_SIZE;
+use firmware::bios::BootEntry;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0xAB, 0xCD, 0xEF];
+
+/// Backend with no DHCP server behind it: records transmitted frames and never receives.
+struct SilentNetwork {
+    tx: Rc<RefCell<Vec<Vec<u8>>>>,
+}
+
+impl NetworkBackend for SilentNetwork {
+    fn transmit(&mut self, frame: Vec<u8>) {
+        self.tx.borrow_mut().push(frame);
+    }
+}
+
+/// Boot sector that stores `marker` at 0000:0500 and halts.
+fn boot_sector(marker: u8) -> Vec<u8> {
+    let mut sector = vec![0u8; SECTOR_SIZE];
+    let code = [
+        0xC6, 0x06, 0x00, 0x05, marker, // mov byte [0x0500], marker
+        0xF4,   // hlt
+        0xEB, 0xFD, // jmp short $-3
+    ];
+    sector[..code.len()].copy_from_slice(&code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+#[test]
+fn network_boot_without_dhcp_server_falls_through_and_keeps_backend() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        boot_order: vec![BootEntry::Network, BootEntry::Hdd(0)],
+        enable_pc_platform: true,
+        enable_e1000: true,
+        e1000_mac_addr: Some(MAC),
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(0xD0)).unwrap();
+    let tx = Rc::new(RefCell::new(Vec::new()));
+    m.set_network_backend(Box::new(SilentNetwork { tx: tx.clone() }));
+    m.reset();
+
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => break,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    assert_eq!(m.booted_entry(), Some(BootEntry::Hdd(0)));
+    assert_eq!(m.read_physical_u8(0x500), 0xD0);
+
+    // POST broadcast (and retransmitted) a DHCPDISCOVER from the E1000's MAC address.
+    let frames = tx.borrow();
+    assert!(!frames.is_empty());
+    for frame in frames.iter() {
+        assert_eq!(&frame[0..6], &[0xFF; 6]);
+        assert_eq!(&frame[6..12], &MAC);
+        // UDP 68 -> 67.
+        assert_eq!(&frame[34..38], &[0, 68, 0, 67]);
+    }
+    drop(frames);
+
+    // The backend is handed back to the NIC after POST.
+    assert_eq!(Rc::strong_count(&tx), 2);
+}
+
+#[test]
+fn boot_menu_lends_backend_only_to_network_boot() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_e1000: true,
+        e1000_mac_addr: Some(MAC),
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(0xD0)).unwrap();
+    let tx = Rc::new(RefCell::new(Vec::new()));
+    m.set_network_backend(Box::new(SilentNetwork { tx: tx.clone() }));
+    m.request_boot_menu();
+    m.reset();
+
+    // The present NIC is listed, but nothing is sent while the menu waits.
+    assert!(m.boot_menu_active());
+    assert!(matches!(m.run_slice(1_000), RunExit::Halted { .. }));
+    assert!(m.boot_menu_active());
+    assert!(tx.borrow().is_empty());
+
+    // Menu order: 1 = HDD0, 2 = network.
+    m.inject_bios_key(0x0332);
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } if !m.boot_menu_active() => break,
+            RunExit::Halted { .. } | RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    assert!(!tx.borrow().is_empty());
+    assert_eq!(m.booted_entry(), Some(BootEntry::Hdd(0)));
+    assert_eq!(m.read_physical_u8(0x500), 0xD0);
+    assert_eq!(Rc::strong_count(&tx), 2);
+}
@@ -0,0 +1,158 @@
+use aero_machine::{
+    DirectBootMode, DirectBootSpec, ExceptionRecord, Machine, MachineConfig, ResetSource, RunExit,
+};
+use aero_platform::reset::ResetKind;
+use pretty_assertions::assert_eq;
+
+const MARKER_ADDR: u64 = 0x2000;
+
+/// Writes 0x06 to port 0xCF9, then a marker byte, then halts.
+fn boot_sector_resets_then_writes_marker() -> [u8; aero_storage::SECTOR_SIZE] {
+    let code: &[&[u8]] = &[
+        &[0xFA],                         // cli
+        &[0x31, 0xC0],                   // xor ax, ax
+        &[0x8E, 0xD8],                   // mov ds, ax
+        &[0xBA, 0xF9, 0x0C],             // mov dx, 0x0CF9
+        &[0xB0, 0x06],                   // mov al, 0x06
+        &[0xEE],                         // out dx, al
+        &[0xC6, 0x06, 0x00, 0x20, 0xAA], // mov byte [0x2000], 0xAA
+        &[0xF4],                         // hlt
+    ];
+    let code = code.concat();
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(&code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: false,
+        enable_serial: false,
+        enable_i8042: true,
+        enable_a20_gate: true,
+        enable_reset_ctrl: true,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector_resets_then_writes_marker().to_vec())
+        .unwrap();
+    m.reset();
+    m
+}
+
+fn expect_reset(m: &mut Machine) -> (ResetKind, ResetSource, u64) {
+    match m.run_slice(10_000) {
+        RunExit::ResetRequested {
+            kind,
+            source,
+            executed,
+        } => (kind, source, executed),
+        other => panic!("unexpected exit: {other:?}"),
+    }
+}
+
+#[test]
+fn port_0xcf9_reset_stays_pending_until_the_host_resets() {
+    let mut m = new_machine();
+    assert_eq!(m.pending_reset(), None);
+
+    let (kind, source, executed) = expect_reset(&mut m);
+    assert_eq!(kind, ResetKind::System);
+    assert_eq!(source, ResetSource::Port0xCF9);
+    assert!(executed > 0);
+    assert_eq!(m.pending_reset(), Some(ResetSource::Port0xCF9));
+
+    // The request is reported again, without executing, until the host acts on it.
+    assert_eq!(
+        expect_reset(&mut m),
+        (ResetKind::System, ResetSource::Port0xCF9, 0)
+    );
+    assert_eq!(m.read_physical_u8(MARKER_ADDR), 0);
+
+    m.reset();
+    assert_eq!(m.pending_reset(), None);
+    assert_eq!(expect_reset(&mut m).1, ResetSource::Port0xCF9);
+}
+
+#[test]
+fn cancelled_reset_resumes_the_guest() {
+    let mut m = new_machine();
+    expect_reset(&mut m);
+    m.cancel_pending_reset();
+    assert_eq!(m.pending_reset(), None);
+
+    match m.run_slice(10_000) {
+        RunExit::Halted { .. } => {}
+        other => panic!("unexpected exit: {other:?}"),
+    }
+    assert_eq!(m.read_physical_u8(MARKER_ADDR), 0xAA);
+}
+
+#[test]
+fn keyboard_controller_reset_is_attributed() {
+    let mut m = new_machine();
+    m.io_write(0x64, 1, 0xFE);
+    assert_eq!(m.pending_reset(), Some(ResetSource::KeyboardController));
+    assert_eq!(
+        expect_reset(&mut m),
+        (ResetKind::System, ResetSource::KeyboardController, 0)
+    );
+}
+
+#[test]
+fn port_0x92_fast_reset_is_attributed() {
+    let mut m = new_machine();
+    m.io_write(0x92, 1, 0x03);
+    assert_eq!(
+        expect_reset(&mut m),
+        (ResetKind::System, ResetSource::Port0x92, 0)
+    );
+}
+
+#[test]
+fn system_reset_source_wins_over_earlier_cpu_reset() {
+    let mut m = new_machine();
+    m.io_write(0xCF9, 1, 0x05); // RESET_ENABLE | CPU_RESET
+    m.io_write(0xCF9, 1, 0x05);
+    assert_eq!(m.pending_reset(), Some(ResetSource::Port0xCF9));
+    m.io_write(0x64, 1, 0xFE);
+    m.io_write(0x92, 1, 0x03);
+    assert_eq!(
+        expect_reset(&mut m),
+        (ResetKind::System, ResetSource::KeyboardController, 0)
+    );
+}
+
+#[test]
+fn triple_fault_reports_exception_history() {
+    const ENTRY: u64 = 0x10_0000;
+
+    let mut m = new_machine();
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Protected32,
+        entry: ENTRY,
+        blobs: vec![(ENTRY, vec![0xEB, 0xFE])], // jmp $
+        ..Default::default()
+    })
+    .unwrap();
+    // With an empty IDT every delivery raises #GP, escalating to #DF and then a triple fault.
+    m.cpu_mut().tables.idtr.limit = 0;
+    m.inject_exception(0, 6, None).unwrap();
+
+    let (kind, source, _) = expect_reset(&mut m);
+    assert_eq!(kind, ResetKind::Cpu);
+    let record = |vector| ExceptionRecord { vector, rip: ENTRY };
+    assert_eq!(
+        source,
+        ResetSource::TripleFault {
+            vector_history: vec![record(6), record(13), record(13), record(8), record(13)],
+        }
+    );
+    assert_eq!(m.pending_reset(), Some(source));
+
+    m.reset_with_kind(kind);
+    assert_eq!(m.pending_reset(), None);
+}
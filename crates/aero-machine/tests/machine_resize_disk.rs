@@ -0,0 +1,77 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::profile::VIRTIO_DEVICE_CFG_BAR0_OFFSET;
+use aero_devices::pci::PciDevice as _;
+use aero_machine::{Machine, MachineConfig, MachineError};
+use aero_storage::{DiskError, VirtualDisk as _, SECTOR_SIZE};
+
+const PORT0_IS: u64 = 0x100 + 0x10;
+const PORT0_SERR: u64 = 0x100 + 0x30;
+const PORT_IS_PCS: u64 = 1 << 6;
+const SERR_DIAG_X: u64 = 1 << 26;
+
+#[test]
+fn resize_disk_updates_controllers_and_signals_guest() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_ahci: true,
+        enable_virtio_blk: true,
+        // Keep the machine minimal and deterministic for a focused test.
+        enable_nvme: false,
+        enable_ide: false,
+        enable_uhci: false,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(vec![0xA5; 8 * SECTOR_SIZE]).unwrap();
+
+    m.resize_disk(32 * SECTOR_SIZE as u64).unwrap();
+
+    let mut disk = m.shared_disk();
+    assert_eq!(disk.capacity_bytes(), 32 * SECTOR_SIZE as u64);
+    let mut sector = [0u8; SECTOR_SIZE];
+    disk.read_sectors(7, &mut sector).unwrap();
+    assert_eq!(sector, [0xA5; SECTOR_SIZE]);
+    disk.read_sectors(31, &mut sector).unwrap();
+    assert_eq!(sector, [0; SECTOR_SIZE]);
+
+    // virtio-blk reports the new capacity (in sectors) in its device config.
+    let virtio_blk = m.virtio_blk().expect("virtio-blk should be enabled");
+    {
+        let command = virtio_blk.borrow().config().command();
+        virtio_blk
+            .borrow_mut()
+            .set_pci_command(command | (1 << 1) | (1 << 2));
+    }
+    let mut cap_bytes = [0u8; 8];
+    virtio_blk
+        .borrow_mut()
+        .bar0_read(VIRTIO_DEVICE_CFG_BAR0_OFFSET as u64, &mut cap_bytes);
+    assert_eq!(u64::from_le_bytes(cap_bytes), 32);
+
+    // AHCI latches a port change so the guest driver re-enumerates the disk.
+    let ahci = m.ahci().expect("AHCI should be enabled");
+    {
+        let mut ahci = ahci.borrow_mut();
+        let command = ahci.config().command();
+        ahci.config_mut().set_command(command | (1 << 1));
+        assert_ne!(ahci.mmio_read(PORT0_IS, 4) & PORT_IS_PCS, 0);
+        assert_ne!(ahci.mmio_read(PORT0_SERR, 4) & SERR_DIAG_X, 0);
+    }
+
+    // Shrinking is rejected and leaves the disk untouched.
+    assert_eq!(
+        m.resize_disk(16 * SECTOR_SIZE as u64),
+        Err(MachineError::Disk(DiskError::Unsupported(
+            "shrinking a disk"
+        )))
+    );
+    assert_eq!(m.shared_disk().capacity_bytes(), 32 * SECTOR_SIZE as u64);
+}
@@ -0,0 +1,204 @@
+use aero_devices::pci::profile::{ISA_PIIX3, SATA_AHCI_ICH9};
+use aero_devices::pci::{PciBdf, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig, MachineError};
+use aero_platform::interrupts::{InterruptController, PlatformInterruptMode};
+use aero_storage::{MemBackend, RawDisk, SECTOR_SIZE};
+use pretty_assertions::assert_eq;
+
+const IOAPIC2_BASE: u64 = 0xFEC0_1000;
+const GHC: u64 = 0x04;
+const GHC_IE: u64 = 1 << 1;
+const GHC_AE: u64 = 1 << 31;
+const PORT0_IE: u64 = 0x100 + 0x14;
+const PORT_IS_PCS: u64 = 1 << 6;
+
+// AHCI sits at 00:02.0, so its INTA# swizzles onto PIRQC; hardwire that PIRQ to GSI 26 (pin 2 of
+// the second IOAPIC).
+const AHCI_PIRQ: usize = 2;
+const AHCI_GSI: u32 = 26;
+const PIRQ_TO_GSI: [u32; 4] = [10, 11, AHCI_GSI, 13];
+
+fn cfg_addr(bdf: PciBdf, offset: u8) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device) << 11)
+        | (u32::from(bdf.function) << 8)
+        | u32::from(offset & 0xFC)
+}
+
+fn read_cfg_u8(m: &mut Machine, bdf: PciBdf, offset: u8) -> u8 {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_read(PCI_CFG_DATA_PORT + u16::from(offset & 3), 1) as u8
+}
+
+fn write_cfg_u8(m: &mut Machine, bdf: PciBdf, offset: u8, value: u8) {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_write(
+        PCI_CFG_DATA_PORT + u16::from(offset & 3),
+        1,
+        u32::from(value),
+    );
+}
+
+fn read_u32_le(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+fn find_table(m: &mut Machine, signature: &[u8; 4]) -> Option<Vec<u8>> {
+    let rsdp_addr = m.acpi_rsdp_addr().expect("ACPI RSDP should be published");
+    let rsdp = m.read_physical_bytes(rsdp_addr, 36);
+    let xsdt_addr = u64::from_le_bytes(rsdp[24..32].try_into().unwrap());
+    let xsdt_len = read_u32_le(&m.read_physical_bytes(xsdt_addr, 36), 4) as usize;
+    let xsdt = m.read_physical_bytes(xsdt_addr, xsdt_len);
+    xsdt[36..]
+        .chunks_exact(8)
+        .map(|ent| u64::from_le_bytes(ent.try_into().unwrap()))
+        .map(|addr| {
+            let len = read_u32_le(&m.read_physical_bytes(addr, 36), 4) as usize;
+            m.read_physical_bytes(addr, len)
+        })
+        .find(|table| &table[0..4] == signature)
+}
+
+/// Returns `(id, address, gsi_base)` for every MADT I/O APIC structure.
+fn madt_io_apics(madt: &[u8]) -> Vec<(u8, u32, u32)> {
+    let mut out = Vec::new();
+    let mut off = 44;
+    while off < madt.len() {
+        let len = madt[off + 1] as usize;
+        if madt[off] == 1 {
+            out.push((
+                madt[off + 2],
+                read_u32_le(madt, off + 4),
+                read_u32_le(madt, off + 8),
+            ));
+        }
+        off += len;
+    }
+    out
+}
+
+fn machine(enable_second_ioapic: bool, pci_pirq_to_gsi: [u32; 4]) -> Result<Machine, MachineError> {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        enable_ahci: true,
+        enable_ide: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        enable_second_ioapic,
+        pci_pirq_to_gsi,
+        ..Default::default()
+    })
+}
+
+fn program_ioapic2_entry(m: &mut Machine, pin: u32, low: u32) {
+    m.write_physical_u32(IOAPIC2_BASE, 0x10 + pin * 2);
+    m.write_physical_u32(IOAPIC2_BASE + 0x10, low);
+    m.write_physical_u32(IOAPIC2_BASE, 0x10 + pin * 2 + 1);
+    m.write_physical_u32(IOAPIC2_BASE + 0x10, 0);
+}
+
+fn read_ioapic2_entry_low(m: &mut Machine, pin: u32) -> u32 {
+    m.write_physical_u32(IOAPIC2_BASE, 0x10 + pin * 2);
+    m.read_physical_u32(IOAPIC2_BASE + 0x10)
+}
+
+fn assert_ahci_intx(m: &mut Machine) {
+    let disk = RawDisk::create(MemBackend::new(), 8 * SECTOR_SIZE as u64).unwrap();
+    m.attach_ahci_disk_port0(Box::new(disk)).unwrap();
+
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(SATA_AHCI_ICH9.bdf, 0x04));
+    m.io_write(PCI_CFG_DATA_PORT, 2, 0x0006);
+    m.poll_pci_intx_lines();
+
+    let ahci = m.ahci().unwrap();
+    {
+        let mut ahci = ahci.borrow_mut();
+        ahci.mmio_write(GHC, 4, GHC_AE | GHC_IE);
+        ahci.mmio_write(PORT0_IE, 4, PORT_IS_PCS);
+        ahci.notify_device_changed(0);
+    }
+    m.poll_pci_intx_lines();
+}
+
+#[test]
+fn madt_describes_both_ioapics() {
+    let mut m = machine(true, PIRQ_TO_GSI).unwrap();
+    let madt = find_table(&mut m, b"APIC").expect("MADT should be published");
+    assert_eq!(
+        madt_io_apics(&madt),
+        vec![(0, 0xFEC0_0000, 0), (1, 0xFEC0_1000, 24)]
+    );
+
+    // The second IOAPIC answers at its MMIO window with ID 1 and 24 redirection entries.
+    m.write_physical_u32(IOAPIC2_BASE, 0x00);
+    assert_eq!(m.read_physical_u32(IOAPIC2_BASE + 0x10), 1 << 24);
+    m.write_physical_u32(IOAPIC2_BASE, 0x01);
+    assert_eq!(m.read_physical_u32(IOAPIC2_BASE + 0x10) >> 16, 23);
+
+    let mut single = machine(false, [10, 11, 12, 13]).unwrap();
+    let madt = find_table(&mut single, b"APIC").unwrap();
+    assert_eq!(madt_io_apics(&madt), vec![(0, 0xFEC0_0000, 0)]);
+}
+
+#[test]
+fn pci_intx_on_gsi_26_delivers_through_second_ioapic() {
+    const VECTOR: u8 = 0x62;
+    let pin = AHCI_GSI - 24;
+
+    let mut m = machine(true, PIRQ_TO_GSI).unwrap();
+    // PIRQRC cannot express GSI 26: the PIRQ reads back as disabled and guest writes don't move it.
+    assert_eq!(
+        read_cfg_u8(&mut m, ISA_PIIX3.bdf, 0x60 + AHCI_PIRQ as u8),
+        0x80
+    );
+    write_cfg_u8(&mut m, ISA_PIIX3.bdf, 0x60 + AHCI_PIRQ as u8, 11);
+    // BIOS POST programs the interrupt line from the same table.
+    assert_eq!(
+        read_cfg_u8(&mut m, SATA_AHCI_ICH9.bdf, 0x3C),
+        AHCI_GSI as u8
+    );
+
+    let interrupts = m.platform_interrupts().unwrap();
+    interrupts
+        .borrow_mut()
+        .set_mode(PlatformInterruptMode::Apic);
+    program_ioapic2_entry(&mut m, pin, u32::from(VECTOR) | (1 << 13) | (1 << 15));
+
+    assert_ahci_intx(&mut m);
+    assert_eq!(
+        m.pci_intx_router().unwrap().borrow().pirq_route(AHCI_PIRQ),
+        Some(AHCI_GSI)
+    );
+    assert!(interrupts.borrow().gsi_level(AHCI_GSI));
+    assert!(!interrupts.borrow().gsi_level(11));
+
+    let mut ints = interrupts.borrow_mut();
+    assert_eq!(InterruptController::get_pending(&*ints), Some(VECTOR));
+    InterruptController::acknowledge(&mut *ints, VECTOR);
+    drop(ints);
+    // Level-triggered delivery latched Remote IRR in the second IOAPIC's redirection entry.
+    assert_ne!(read_ioapic2_entry_low(&mut m, pin) & (1 << 14), 0);
+}
+
+#[test]
+fn gsis_beyond_the_first_ioapic_require_the_second() {
+    match machine(false, PIRQ_TO_GSI) {
+        Err(MachineError::InvalidPciPirqGsi { pirq, gsi }) => {
+            assert_eq!((pirq, gsi), (AHCI_PIRQ, AHCI_GSI));
+        }
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+    assert!(machine(false, [10, 11, 20, 13]).is_ok());
+    assert!(matches!(
+        machine(true, [10, 11, 48, 13]),
+        Err(MachineError::InvalidPciPirqGsi { pirq: 2, gsi: 48 })
+    ));
+    assert!(matches!(
+        machine(true, [10, 11, 8, 13]),
+        Err(MachineError::InvalidPciPirqGsi { pirq: 2, gsi: 8 })
+    ));
+}
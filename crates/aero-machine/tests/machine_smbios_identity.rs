@@ -0,0 +1,107 @@
+use aero_machine::{Machine, MachineConfig};
+use firmware::smbios::{
+    find_eps, parse_eps_table_info, parse_structures, validate_eps_checksum, SmbiosIdentity,
+};
+use pretty_assertions::assert_eq;
+
+fn boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+/// Returns the strings referenced by the string-number bytes at `offsets` of the first SMBIOS
+/// structure of type `ty`.
+fn read_strings(m: &mut Machine, ty: u8, offsets: &[usize]) -> Vec<Option<String>> {
+    let eps_addr = find_eps(m).expect("SMBIOS EPS not found after BIOS POST");
+    let eps = m.read_physical_bytes(eps_addr, 0x1F);
+    assert!(validate_eps_checksum(&eps));
+
+    let table_info = parse_eps_table_info(&eps).expect("invalid SMBIOS EPS");
+    let table = m.read_physical_bytes(table_info.table_addr, table_info.table_len);
+    let structures = parse_structures(&table);
+    let s = structures
+        .iter()
+        .find(|s| s.header.ty == ty)
+        .unwrap_or_else(|| panic!("Type {ty} missing from SMBIOS table"));
+
+    let strings: Vec<&[u8]> = s.strings.split(|&b| b == 0).collect();
+    offsets
+        .iter()
+        .map(|&off| match s.formatted[off] {
+            0 => None,
+            idx => Some(String::from_utf8_lossy(strings[usize::from(idx) - 1]).into_owned()),
+        })
+        .collect()
+}
+
+fn some(s: &str) -> Option<String> {
+    Some(s.to_string())
+}
+
+#[test]
+fn smbios_identity_is_published_and_survives_snapshot_restore() {
+    let identity = SmbiosIdentity {
+        system_manufacturer: some("Contoso"),
+        system_product_name: some("Widget 3000"),
+        system_serial_number: some("SN-42"),
+        baseboard_manufacturer: some("Fabrikam"),
+        chassis_type: Some(0x0A),
+        oem_strings: vec!["alpha".to_string()],
+        ..Default::default()
+    };
+
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        smbios: identity.clone(),
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector().to_vec()).unwrap();
+    m.reset();
+
+    let effective = m.smbios_identity();
+    assert_eq!(effective, identity.effective());
+    assert_eq!(
+        read_strings(&mut m, 1, &[4, 5, 6, 7]),
+        vec![
+            some("Contoso"),
+            some("Widget 3000"),
+            effective.system_version.clone(),
+            some("SN-42"),
+        ]
+    );
+    assert_eq!(
+        read_strings(&mut m, 2, &[4, 5]),
+        vec![some("Fabrikam"), effective.baseboard_product.clone()]
+    );
+    // Type 11 formatted byte 4 is the OEM string count; string numbers are implicit (1..=count).
+    assert_eq!(read_strings(&mut m, 11, &[]), Vec::<Option<String>>::new());
+
+    let snap = m.take_snapshot_full().unwrap();
+
+    let mut restored = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        ..Default::default()
+    })
+    .unwrap();
+    restored.set_disk_image(boot_sector().to_vec()).unwrap();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    assert_eq!(restored.smbios_identity(), effective);
+
+    // The identity is part of the firmware configuration, so a guest reboot keeps it.
+    restored.reset();
+    assert_eq!(
+        read_strings(&mut restored, 1, &[4, 7]),
+        vec![some("Contoso"), some("SN-42")]
+    );
+
+    // Host updates apply on the next reset.
+    restored.set_smbios_identity(SmbiosIdentity::default());
+    restored.reset();
+    assert_eq!(
+        read_strings(&mut restored, 1, &[4, 7]),
+        vec![some("Aero"), some("00000000")]
+    );
+}
@@ -0,0 +1,77 @@
+use aero_machine::{
+    Machine, MachineConfig, StorageFaultCounters, StorageFaultDelay, StorageFaultPolicy,
+};
+use aero_virtio::devices::blk::VirtioBlk;
+
+fn machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_ahci: true,
+        enable_nvme: true,
+        enable_ide: true,
+        enable_virtio_blk: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn injectors_installed(m: &Machine) -> [bool; 4] {
+    [
+        m.ahci().unwrap().borrow().fault_injector().is_some(),
+        m.nvme()
+            .unwrap()
+            .borrow()
+            .controller()
+            .fault_injector()
+            .is_some(),
+        m.ide()
+            .unwrap()
+            .borrow()
+            .controller
+            .fault_injector()
+            .is_some(),
+        m.virtio_blk()
+            .unwrap()
+            .borrow()
+            .device::<VirtioBlk>()
+            .unwrap()
+            .fault_injector()
+            .is_some(),
+    ]
+}
+
+#[test]
+fn storage_fault_policy_is_installed_on_every_controller_and_survives_reset() {
+    let mut m = machine();
+    assert_eq!(injectors_installed(&m), [false; 4]);
+
+    let policy = StorageFaultPolicy {
+        delay: StorageFaultDelay::Fixed { ns: 5_000_000 },
+        ..Default::default()
+    };
+    m.set_storage_fault_policy(Some(policy.clone()));
+    assert_eq!(m.storage_fault_policy(), Some(&policy));
+    assert_eq!(injectors_installed(&m), [true; 4]);
+    assert_eq!(m.storage_fault_counters(), StorageFaultCounters::default());
+
+    m.reset();
+    assert_eq!(injectors_installed(&m), [true; 4]);
+    assert_eq!(
+        m.ahci()
+            .unwrap()
+            .borrow()
+            .fault_injector()
+            .unwrap()
+            .policy(),
+        &policy
+    );
+
+    m.set_storage_fault_policy(None);
+    assert_eq!(injectors_installed(&m), [false; 4]);
+    m.reset();
+    assert_eq!(injectors_installed(&m), [false; 4]);
+}
@@ -415,11 +415,11 @@ impl MemIso {
 }
 
 impl IsoBackend for MemIso {
-    fn sector_count(&self) -> u32 {
-        self.sector_count
+    fn sector_count(&self) -> u64 {
+        u64::from(self.sector_count)
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         if !buf.len().is_multiple_of(2048) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -0,0 +1,241 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::tpm::{
+    tpm_response_code, TpmBackend, TPM2_CC_GET_RANDOM, TPM2_CC_STARTUP, TPM_CRB_MMIO_BASE,
+    TPM_RC_FAILURE, TPM_RC_SUCCESS,
+};
+use aero_io_snapshot::io::state::{SnapshotError, SnapshotResult};
+use aero_machine::{Machine, MachineConfig};
+use firmware::acpi::{
+    parse_header, parse_rsdp_v2, parse_xsdt_entries, ACPI_HEADER_SIZE, RSDP_V2_SIZE,
+};
+use pretty_assertions::assert_eq;
+
+const LOC_STATE: u64 = TPM_CRB_MMIO_BASE;
+const LOC_CTRL: u64 = TPM_CRB_MMIO_BASE + 0x08;
+const LOC_STS: u64 = TPM_CRB_MMIO_BASE + 0x0C;
+const INTF_ID: u64 = TPM_CRB_MMIO_BASE + 0x30;
+const CTRL_REQ: u64 = TPM_CRB_MMIO_BASE + 0x40;
+const CTRL_STS: u64 = TPM_CRB_MMIO_BASE + 0x44;
+const CTRL_START: u64 = TPM_CRB_MMIO_BASE + 0x4C;
+const CMD_LADDR: u64 = TPM_CRB_MMIO_BASE + 0x5C;
+const DATA_BUFFER: u64 = TPM_CRB_MMIO_BASE + 0x80;
+
+/// Minimal backend: tracks `TPM2_Startup` and serves `TPM2_GetRandom` from a counter.
+#[derive(Default)]
+struct CounterTpm {
+    started: bool,
+    next: u8,
+}
+
+impl TpmBackend for CounterTpm {
+    fn execute(&mut self, command: &[u8]) -> Vec<u8> {
+        let code = u32::from_be_bytes(command[6..10].try_into().unwrap());
+        match code {
+            TPM2_CC_STARTUP => {
+                self.started = true;
+                tpm_response_code(TPM_RC_SUCCESS)
+            }
+            TPM2_CC_GET_RANDOM if self.started => {
+                let count = u16::from_be_bytes(command[10..12].try_into().unwrap());
+                let mut out = Vec::new();
+                out.extend_from_slice(&0x8001u16.to_be_bytes());
+                out.extend_from_slice(&(12 + u32::from(count)).to_be_bytes());
+                out.extend_from_slice(&TPM_RC_SUCCESS.to_be_bytes());
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    out.push(self.next);
+                    self.next = self.next.wrapping_add(1);
+                }
+                out
+            }
+            _ => tpm_response_code(TPM_RC_FAILURE),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.started = false;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        vec![self.started as u8, self.next]
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
+        let [started, next] = bytes else {
+            return Err(SnapshotError::InvalidFieldEncoding("counter tpm state"));
+        };
+        self.started = *started != 0;
+        self.next = *next;
+        Ok(())
+    }
+}
+
+fn machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        enable_tpm: true,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn command(code: u32, params: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x8001u16.to_be_bytes());
+    out.extend_from_slice(&(10 + params.len() as u32).to_be_bytes());
+    out.extend_from_slice(&code.to_be_bytes());
+    out.extend_from_slice(params);
+    out
+}
+
+fn startup() -> Vec<u8> {
+    // TPM_SU_CLEAR.
+    command(TPM2_CC_STARTUP, &0u16.to_be_bytes())
+}
+
+fn get_random(count: u16) -> Vec<u8> {
+    command(TPM2_CC_GET_RANDOM, &count.to_be_bytes())
+}
+
+fn submit(m: &mut Machine, cmd: &[u8]) -> Vec<u8> {
+    m.write_physical(DATA_BUFFER, cmd);
+    m.write_physical_u32(CTRL_START, 1);
+    assert_eq!(
+        m.read_physical_u32(CTRL_START),
+        0,
+        "START must clear on completion"
+    );
+    let header = m.read_physical_bytes(DATA_BUFFER, 10);
+    let len = u32::from_be_bytes(header[2..6].try_into().unwrap()) as usize;
+    m.read_physical_bytes(DATA_BUFFER, len)
+}
+
+fn response_code(rsp: &[u8]) -> u32 {
+    u32::from_be_bytes(rsp[6..10].try_into().unwrap())
+}
+
+fn open_locality(m: &mut Machine) {
+    m.write_physical_u32(LOC_CTRL, 1); // requestAccess
+    assert_eq!(m.read_physical_u32(LOC_STS) & 1, 1, "locality 0 granted");
+    m.write_physical_u32(CTRL_REQ, 1); // cmdReady
+    assert_eq!(m.read_physical_u32(CTRL_STS) & 2, 0, "tpmIdle cleared");
+}
+
+#[test]
+fn tpm_crb_runs_startup_and_get_random_through_mmio() {
+    let mut m = machine();
+
+    // CRB interface, CRB selected, 64-byte transfers; buffer addresses point into the window.
+    let intf_id = m.read_physical_u32(INTF_ID);
+    assert_eq!(intf_id & 0xF, 1);
+    assert_eq!((intf_id >> 14) & 1, 1);
+    assert_eq!(m.read_physical_u32(CMD_LADDR) as u64, DATA_BUFFER);
+    assert_eq!(m.read_physical_u32(CTRL_STS) & 2, 2, "starts idle");
+    assert_eq!(m.read_physical_u32(LOC_STATE) & 0x82, 0x80);
+
+    open_locality(&mut m);
+    assert_eq!(m.read_physical_u32(LOC_STATE) & 0x82, 0x82);
+
+    // The default null backend accepts Startup and rejects everything else.
+    assert_eq!(response_code(&submit(&mut m, &startup())), TPM_RC_SUCCESS);
+    assert_eq!(
+        response_code(&submit(&mut m, &get_random(8))),
+        TPM_RC_FAILURE
+    );
+
+    assert!(m.set_tpm_backend(Box::new(CounterTpm::default())));
+    assert_eq!(
+        response_code(&submit(&mut m, &get_random(4))),
+        TPM_RC_FAILURE,
+        "GetRandom before Startup"
+    );
+    assert_eq!(response_code(&submit(&mut m, &startup())), TPM_RC_SUCCESS);
+    let rsp = submit(&mut m, &get_random(8));
+    assert_eq!(response_code(&rsp), TPM_RC_SUCCESS);
+    assert_eq!(&rsp[10..12], &8u16.to_be_bytes());
+    assert_eq!(&rsp[12..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+    // START is ignored once the locality is relinquished.
+    m.write_physical_u32(LOC_CTRL, 2);
+    assert_eq!(m.read_physical_u32(LOC_STS) & 1, 0);
+    m.write_physical(DATA_BUFFER, &get_random(2));
+    m.write_physical_u32(CTRL_START, 1);
+    assert_eq!(m.read_physical_bytes(DATA_BUFFER, 12), get_random(2));
+
+    // Guest reset sends `_TPM_Init`: the backend (and its counter) persist, Startup is required.
+    m.reset();
+    assert_eq!(m.read_physical_u32(LOC_STS) & 1, 0);
+    open_locality(&mut m);
+    assert_eq!(
+        response_code(&submit(&mut m, &get_random(1))),
+        TPM_RC_FAILURE
+    );
+    submit(&mut m, &startup());
+    assert_eq!(submit(&mut m, &get_random(1))[12..], [8]);
+}
+
+#[test]
+fn tpm_crb_snapshot_restores_registers_and_backend_state() {
+    let mut m = machine();
+    m.set_tpm_backend(Box::new(CounterTpm::default()));
+    open_locality(&mut m);
+    submit(&mut m, &startup());
+    submit(&mut m, &get_random(5));
+
+    let snap = m.take_snapshot_full().unwrap();
+
+    let mut restored = machine();
+    restored.set_tpm_backend(Box::new(CounterTpm::default()));
+    restored.restore_snapshot_bytes(&snap).unwrap();
+
+    assert_eq!(restored.read_physical_u32(LOC_STS) & 1, 1);
+    assert_eq!(restored.read_physical_u32(CTRL_STS) & 2, 0);
+    let rsp = submit(&mut restored, &get_random(3));
+    assert_eq!(response_code(&rsp), TPM_RC_SUCCESS);
+    assert_eq!(&rsp[12..], &[5, 6, 7]);
+}
+
+fn find_table(m: &mut Machine, signature: &[u8; 4]) -> Option<Vec<u8>> {
+    let rsdp_addr = m.acpi_rsdp_addr().expect("firmware should publish an RSDP");
+    let rsdp = parse_rsdp_v2(&m.read_physical_bytes(rsdp_addr, RSDP_V2_SIZE)).unwrap();
+    let xsdt_hdr =
+        parse_header(&m.read_physical_bytes(rsdp.xsdt_address, ACPI_HEADER_SIZE)).unwrap();
+    let xsdt = m.read_physical_bytes(rsdp.xsdt_address, xsdt_hdr.length as usize);
+    parse_xsdt_entries(&xsdt)
+        .unwrap()
+        .into_iter()
+        .find_map(|addr| {
+            let hdr = parse_header(&m.read_physical_bytes(addr, ACPI_HEADER_SIZE))?;
+            (&hdr.signature == signature).then(|| m.read_physical_bytes(addr, hdr.length as usize))
+        })
+}
+
+#[test]
+fn tpm2_acpi_table_is_published_only_when_enabled() {
+    let mut m = machine();
+    let tpm2 = find_table(&mut m, b"TPM2").expect("TPM2 table should be published");
+    // Control area address and CRB start method.
+    assert_eq!(
+        u64::from_le_bytes(tpm2[40..48].try_into().unwrap()),
+        TPM_CRB_MMIO_BASE + 0x40
+    );
+    assert_eq!(u32::from_le_bytes(tpm2[48..52].try_into().unwrap()), 7);
+
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(find_table(&mut m, b"TPM2").is_none());
+    assert!(m.tpm().is_none());
+    assert!(!m.set_tpm_backend(Box::new(CounterTpm::default())));
+}
@@ -0,0 +1,122 @@
+use aero_cpu_core::state::CpuMode;
+use aero_cpu_core::Exception;
+use aero_machine::{Machine, MachineConfig, RefusedMsrAccess, RunExit, ZeroCpuidLeaf};
+use pretty_assertions::assert_eq;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn machine_with_boot_code(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    // Drop anything firmware POST contributed.
+    let _ = m.take_unimplemented_report();
+    m
+}
+
+fn run_until_exception(m: &mut Machine) -> Exception {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Exception { exception, .. } => return exception,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not fault");
+}
+
+#[test]
+fn unimplemented_instructions_are_deduplicated_across_resets() {
+    // ud2
+    let mut m = machine_with_boot_code(&[0x0F, 0x0B]);
+    assert_eq!(run_until_exception(&mut m), Exception::InvalidOpcode);
+    m.reset();
+    assert_eq!(run_until_exception(&mut m), Exception::InvalidOpcode);
+
+    let report = m.take_unimplemented_report();
+    assert_eq!(report.instructions.len(), 1, "{report:?}");
+    let ud2 = &report.instructions[0];
+    assert_eq!(ud2.rip, 0x7C00);
+    assert_eq!(ud2.bytes.len(), 15);
+    assert_eq!(&ud2.bytes[..2], &[0x0F, 0x0B]);
+    assert_eq!(ud2.len, 2);
+    assert_eq!(ud2.mode, CpuMode::Real);
+    assert_eq!(ud2.bitness, 16);
+    assert_eq!(ud2.mnemonic.as_deref(), Some("ud2"));
+    assert_eq!(ud2.exception, Exception::InvalidOpcode);
+    assert_eq!(ud2.hits, 2);
+    assert!(report.refused_msrs.is_empty());
+    assert_eq!(report.dropped, 0);
+
+    // Taking the report resets the collector.
+    assert!(m.take_unimplemented_report().is_empty());
+}
+
+#[test]
+fn refused_msrs_and_retried_zero_cpuid_leaves_are_reported() {
+    let code = [
+        0x66, 0xB8, 0x00, 0x00, 0x00, 0x40, // mov eax, 0x4000_0000
+        0x66, 0x31, 0xC9, // xor ecx, ecx
+        0x0F, 0xA2, // cpuid
+        0x66, 0xB8, 0x00, 0x00, 0x00, 0x40, // mov eax, 0x4000_0000
+        0x66, 0x31, 0xC9, // xor ecx, ecx
+        0x0F, 0xA2, // cpuid
+        0x66, 0xB8, 0x06, 0x00, 0x00, 0x00, // mov eax, 6
+        0x0F, 0xA2, // cpuid (all-zero, but only probed once)
+        0x66, 0xB9, 0xAD, 0xDE, 0x00, 0x00, // mov ecx, 0xDEAD
+        0x0F, 0x32, // rdmsr
+    ];
+    let mut m = machine_with_boot_code(&code);
+    assert_eq!(run_until_exception(&mut m), Exception::gp0());
+
+    let report = m.take_unimplemented_report();
+    assert_eq!(
+        report.refused_msrs,
+        vec![RefusedMsrAccess {
+            msr: 0xDEAD,
+            write: false,
+            last_value: None,
+            rip: 0x7C00 + code.len() as u64 - 2,
+            hits: 1,
+        }]
+    );
+    assert_eq!(
+        report.zero_cpuid_leaves,
+        vec![ZeroCpuidLeaf {
+            leaf: 0x4000_0000,
+            subleaf: 0,
+            hits: 2,
+        }]
+    );
+    // A refused MSR is an ordinary #GP, not an unimplemented instruction.
+    assert!(report.instructions.is_empty(), "{report:?}");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn unimplemented_report_serializes_to_json() {
+    let mut m = machine_with_boot_code(&[0x0F, 0x0B]);
+    run_until_exception(&mut m);
+    let json = serde_json::to_value(m.take_unimplemented_report()).unwrap();
+    let ud2 = &json["instructions"][0];
+    assert!(ud2["bytes"].as_str().unwrap().starts_with("0f 0b "));
+    assert_eq!(ud2["mode"], "real");
+    assert_eq!(ud2["mnemonic"], "ud2");
+    assert_eq!(ud2["assist"], serde_json::Value::Null);
+    assert_eq!(ud2["hits"], 1);
+}
@@ -0,0 +1,151 @@
+use aero_machine::{
+    Machine, MachineConfig, RunExit, UnknownAccess, UnknownAccessConfig, UnknownAccessLog,
+    UnknownAccessPolicy, UnknownAccessSpace,
+};
+use pretty_assertions::assert_eq;
+
+/// Above the 2MiB of configured RAM and below any PCI window.
+const UNMAPPED: u64 = 0x0100_0000;
+
+const CODE: [u8; 30] = [
+    0xFA, // cli
+    0x31, 0xC0, // xor ax, ax
+    0x8E, 0xD8, // mov ds, ax
+    0x66, 0xBB, 0x00, 0x00, 0x00, 0x01, // mov ebx, UNMAPPED
+    0x67, 0x8A, 0x03, // 0x7C0B: mov al, [ebx]
+    0x67, 0x88, 0x03, // 0x7C0E: mov [ebx], al
+    0xBA, 0x34, 0x12, // mov dx, 0x1234
+    0xEC, // 0x7C14: in al, dx
+    0xEC, // 0x7C15: in al, dx
+    0xEE, // 0x7C16: out dx, al
+    0xE6, 0x80, // out 0x80, al (ignored by default)
+    0xBA, 0xE8, 0x02, // mov dx, 0x2E8
+    0xEC, // in al, dx (ignored by default)
+    0xF4, // hlt
+];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(config: UnknownAccessConfig) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_unknown_access_config(config);
+    m.set_disk_image(boot_sector(&CODE)).unwrap();
+    // The policy is host configuration and must survive reset.
+    m.reset();
+    m
+}
+
+fn access(
+    space: UnknownAccessSpace,
+    addr: u64,
+    write: bool,
+    rip: u64,
+    count: u64,
+) -> UnknownAccess {
+    UnknownAccess {
+        space,
+        addr,
+        size: 1,
+        write,
+        rip,
+        count,
+    }
+}
+
+#[test]
+fn log_policy_records_deduplicated_accesses() {
+    let mut m = new_machine(UnknownAccessConfig {
+        port_io: UnknownAccessPolicy::Log,
+        mmio: UnknownAccessPolicy::Log,
+        ..Default::default()
+    });
+    assert!(matches!(m.run_slice(10_000), RunExit::Halted { .. }));
+
+    assert_eq!(
+        m.take_unknown_access_log(),
+        UnknownAccessLog {
+            entries: vec![
+                access(UnknownAccessSpace::Mmio, UNMAPPED, false, 0x7C0B, 1),
+                access(UnknownAccessSpace::Mmio, UNMAPPED, true, 0x7C0E, 1),
+                access(UnknownAccessSpace::PortIo, 0x1234, false, 0x7C15, 2),
+                access(UnknownAccessSpace::PortIo, 0x1234, true, 0x7C16, 1),
+            ],
+            dropped: 0,
+        }
+    );
+    assert_eq!(m.take_unknown_access_log(), UnknownAccessLog::default());
+
+    // Host accesses are not guest activity.
+    m.read_physical_u8(UNMAPPED);
+    assert_eq!(m.take_unknown_access_log(), UnknownAccessLog::default());
+}
+
+#[test]
+fn trap_policy_stops_the_slice_at_the_first_unknown_access() {
+    let mut m = new_machine(UnknownAccessConfig {
+        mmio: UnknownAccessPolicy::Trap,
+        ..Default::default()
+    });
+    match m.run_slice(10_000) {
+        RunExit::UnknownMmio {
+            paddr, size, write, ..
+        } => assert_eq!((paddr, size, write), (UNMAPPED, 1, false)),
+        other => panic!("unexpected exit: {other:?}"),
+    }
+    // Port I/O stayed silent.
+    let log = m.take_unknown_access_log();
+    assert!(log
+        .entries
+        .iter()
+        .all(|entry| entry.space == UnknownAccessSpace::Mmio));
+
+    let mut m = new_machine(UnknownAccessConfig {
+        port_io: UnknownAccessPolicy::Trap,
+        ..Default::default()
+    });
+    match m.run_slice(10_000) {
+        RunExit::UnknownPortIo {
+            port, size, write, ..
+        } => assert_eq!((port, size, write), (0x1234, 1, false)),
+        other => panic!("unexpected exit: {other:?}"),
+    }
+}
+
+#[test]
+fn ignore_lists_and_silent_policy_suppress_recording() {
+    let mut m = new_machine(UnknownAccessConfig {
+        port_io: UnknownAccessPolicy::Log,
+        mmio: UnknownAccessPolicy::Trap,
+        port_io_ignore: vec![0x80..=0x80, 0x1200..=0x12FF],
+        mmio_ignore: vec![UNMAPPED..=UNMAPPED + 0xFFF],
+    });
+    assert!(matches!(m.run_slice(10_000), RunExit::Halted { .. }));
+    // The default ignore list was replaced, so the COM4 probe shows up.
+    let log = m.take_unknown_access_log();
+    assert_eq!(
+        log.entries
+            .iter()
+            .map(|entry| (entry.space, entry.addr))
+            .collect::<Vec<_>>(),
+        vec![(UnknownAccessSpace::PortIo, 0x2E8)]
+    );
+
+    let mut m = new_machine(UnknownAccessConfig::default());
+    assert!(matches!(m.run_slice(10_000), RunExit::Halted { .. }));
+    assert_eq!(m.take_unknown_access_log(), UnknownAccessLog::default());
+}
@@ -4,8 +4,11 @@ use aero_devices::pci::profile::{USB_EHCI_ICH9, USB_UHCI_PIIX3};
 use aero_devices::usb::ehci::EhciPciDevice;
 use aero_devices::usb::uhci::regs as uhci_regs;
 use aero_machine::{Machine, MachineConfig};
-use aero_usb::ehci::regs::{reg_portsc, CONFIGFLAG_CF, PORTSC_PO, PORTSC_PR, REG_CONFIGFLAG};
+use aero_usb::ehci::regs::{
+    reg_portsc, CONFIGFLAG_CF, PORTSC_CCS, PORTSC_PO, PORTSC_PP, PORTSC_PR, REG_CONFIGFLAG,
+};
 use aero_usb::hid::UsbHidKeyboardHandle;
+use aero_usb::usb2_port::Usb2PortOwner;
 
 #[test]
 fn machine_usb2_companion_routing_moves_devices_between_uhci_and_ehci() {
@@ -226,3 +229,205 @@ fn machine_reset_restores_usb2_mux_routing_to_companion_controller() {
         "expected reset to route muxed port back to companion controller"
     );
 }
+
+#[test]
+fn machine_usb_physical_port_hands_full_speed_device_to_companion_controller() {
+    let cfg = MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_uhci: true,
+        enable_ehci: true,
+        // Keep the machine minimal/deterministic for this routing test.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    };
+
+    let mut m = Machine::new(cfg).unwrap();
+
+    let (uhci_io_base, ehci_mmio_base) = {
+        let pci_cfg = m
+            .pci_config_ports()
+            .expect("pc platform should expose pci_cfg");
+        let mut pci_cfg = pci_cfg.borrow_mut();
+        let bus = pci_cfg.bus_mut();
+
+        let uhci_cfg = bus
+            .device_config_mut(USB_UHCI_PIIX3.bdf)
+            .expect("UHCI PCI function should exist");
+        // I/O decoding for the registers, bus mastering for the schedule.
+        uhci_cfg.set_command(uhci_cfg.command() | 0x5);
+        let uhci_bar4_base = uhci_cfg.bar_range(4).map(|range| range.base).unwrap_or(0);
+        let uhci_io_base = u16::try_from(uhci_bar4_base).expect("UHCI BAR4 base should fit in u16");
+
+        let ehci_mmio_base = bus
+            .device_config(USB_EHCI_ICH9.bdf)
+            .expect("EHCI PCI function should exist")
+            .bar_range(EhciPciDevice::MMIO_BAR_INDEX)
+            .map(|range| range.base)
+            .unwrap_or(0);
+
+        (uhci_io_base, ehci_mmio_base)
+    };
+    assert_ne!(ehci_mmio_base, 0, "EHCI BAR0 base should be programmed");
+
+    // With EHCI enabled, physical ports follow the EHCI root hub; the first two are shared with
+    // UHCI.
+    let ehci_ports = {
+        let ehci = m.ehci().expect("EHCI device should exist");
+        let n = ehci.borrow().controller().hub().num_ports();
+        n
+    };
+    assert_eq!(m.usb_physical_port_count(), ehci_ports);
+    assert_eq!(m.usb_physical_port_owner(0), Some(Usb2PortOwner::Companion));
+    assert_eq!(m.usb_physical_port_owner(2), Some(Usb2PortOwner::Ehci));
+    assert_eq!(m.usb_physical_port_owner(ehci_ports as u8), None);
+
+    m.usb_attach_physical_port(0, Box::new(UsbHidKeyboardHandle::new()))
+        .expect("attach should succeed");
+
+    // The guest EHCI driver claims all ports first.
+    m.write_physical_u32(ehci_mmio_base + REG_CONFIGFLAG, CONFIGFLAG_CF);
+    assert_eq!(m.usb_physical_port_owner(0), Some(Usb2PortOwner::Ehci));
+    let portsc = m.read_physical_u32(ehci_mmio_base + reg_portsc(0));
+    assert_ne!(
+        portsc & PORTSC_CCS,
+        0,
+        "EHCI should see the physically attached device"
+    );
+
+    // The device is not high-speed, so the EHCI driver releases the port to the companion.
+    m.write_physical_u32(ehci_mmio_base + reg_portsc(0), PORTSC_PP | PORTSC_PO);
+    assert_eq!(m.usb_physical_port_owner(0), Some(Usb2PortOwner::Companion));
+
+    // UHCI resets its view of the port and enumerates the device.
+    const UHCI_PORTSC_PR: u32 = 1 << 9;
+    m.io_write(uhci_io_base + uhci_regs::REG_PORTSC1, 2, UHCI_PORTSC_PR);
+    for _ in 0..50 {
+        m.tick_platform(1_000_000);
+    }
+
+    // Enumerate the device through the UHCI schedule: SET_ADDRESS, then GET_DESCRIPTOR(Device).
+    m.io_write(uhci_io_base + uhci_regs::REG_FLBASEADD, 4, UHCI_FRAME_LIST);
+    m.io_write(
+        uhci_io_base + uhci_regs::REG_USBCMD,
+        2,
+        u32::from(uhci_regs::USBCMD_RS),
+    );
+    uhci_control_transfer(
+        &mut m,
+        0,
+        [0x00, 0x05, UHCI_DEVICE_ADDRESS, 0, 0, 0, 0, 0],
+        0,
+    );
+    let desc = uhci_control_transfer(
+        &mut m,
+        UHCI_DEVICE_ADDRESS,
+        [0x80, 0x06, 0x00, 0x01, 0, 0, 18, 0],
+        18,
+    );
+    assert_eq!(desc.len(), 18);
+    assert_eq!(&desc[..2], &[18, 0x01], "expected a device descriptor");
+
+    {
+        let uhci = m.uhci().expect("UHCI device should exist");
+        assert!(
+            uhci.borrow_mut()
+                .controller_mut()
+                .hub_mut()
+                .device_mut_for_address(UHCI_DEVICE_ADDRESS)
+                .is_some(),
+            "device should be addressed via UHCI after PORT_OWNER handoff"
+        );
+    }
+    {
+        let ehci = m.ehci().expect("EHCI device should exist");
+        assert!(
+            ehci.borrow_mut()
+                .controller_mut()
+                .hub_mut()
+                .device_mut_for_address(UHCI_DEVICE_ADDRESS)
+                .is_none(),
+            "device should not be reachable via EHCI after PORT_OWNER handoff"
+        );
+    }
+
+    // Unplugging the physical port removes the device from the owning controller.
+    m.usb_detach_physical_port(0)
+        .expect("detach should succeed");
+    let uhci = m.uhci().expect("UHCI device should exist");
+    assert!(uhci.borrow().controller().hub().port_device(0).is_none());
+}
+
+const UHCI_FRAME_LIST: u32 = 0x10000;
+const UHCI_QH: u32 = 0x11000;
+const UHCI_TDS: u32 = 0x11100;
+const UHCI_SETUP_BUF: u32 = 0x12000;
+const UHCI_DATA_BUF: u32 = 0x12100;
+const UHCI_DEVICE_ADDRESS: u8 = 5;
+
+const UHCI_LINK_T: u32 = 1 << 0;
+const UHCI_LINK_Q: u32 = 1 << 1;
+const UHCI_TD_ACTIVE: u32 = 1 << 23;
+const UHCI_TD_STATUS_MASK: u32 = 0xff << 16;
+
+/// Runs a control transfer on endpoint 0 of `addr` through the UHCI schedule and returns the data
+/// stage (at most one IN packet).
+fn uhci_control_transfer(m: &mut Machine, addr: u8, setup: [u8; 8], data_len: usize) -> Vec<u8> {
+    let token = |pid: u32, toggle: bool, len: usize| {
+        let max_len = if len == 0 { 0x7ff } else { len as u32 - 1 };
+        pid | (u32::from(addr) << 8) | (u32::from(toggle) << 19) | (max_len << 21)
+    };
+    let mut tds = vec![(token(0x2d, false, 8), UHCI_SETUP_BUF)];
+    if data_len != 0 {
+        tds.push((token(0x69, true, data_len), UHCI_DATA_BUF));
+    }
+    // Status stage: opposite direction, zero-length, DATA1.
+    let status_pid = if data_len != 0 { 0xe1 } else { 0x69 };
+    tds.push((token(status_pid, true, 0), 0));
+
+    m.write_physical(u64::from(UHCI_SETUP_BUF), &setup);
+    for (i, &(token, buf)) in tds.iter().enumerate() {
+        let td = UHCI_TDS + i as u32 * 0x20;
+        let link = if i + 1 == tds.len() {
+            UHCI_LINK_T
+        } else {
+            td + 0x20
+        };
+        m.write_physical_u32(u64::from(td), link);
+        m.write_physical_u32(u64::from(td + 4), UHCI_TD_ACTIVE | 0x7ff);
+        m.write_physical_u32(u64::from(td + 8), token);
+        m.write_physical_u32(u64::from(td + 12), buf);
+    }
+    m.write_physical_u32(u64::from(UHCI_QH), UHCI_LINK_T);
+    m.write_physical_u32(u64::from(UHCI_QH + 4), UHCI_TDS);
+    for frame in 0..1024u32 {
+        m.write_physical_u32(
+            u64::from(UHCI_FRAME_LIST + frame * 4),
+            UHCI_QH | UHCI_LINK_Q,
+        );
+    }
+
+    for _ in 0..10 {
+        m.tick_platform(1_000_000);
+    }
+    for i in 0..tds.len() {
+        let ctrl = m.read_physical_u32(u64::from(UHCI_TDS + i as u32 * 0x20 + 4));
+        assert_eq!(
+            ctrl & UHCI_TD_STATUS_MASK,
+            0,
+            "TD {i} should complete without error (ctrl={ctrl:#x})"
+        );
+    }
+
+    if data_len == 0 {
+        return Vec::new();
+    }
+    let ctrl = m.read_physical_u32(u64::from(UHCI_TDS + 0x20 + 4));
+    let actual = ((ctrl & 0x7ff) + 1) & 0x7ff;
+    m.read_physical_bytes(u64::from(UHCI_DATA_BUF), actual as usize)
+}
@@ -0,0 +1,144 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_machine::{Machine, MachineConfig, ProxyHandle};
+use aero_usb::passthrough::UsbHostAction;
+use aero_usb::{ProxyUsbDescriptors, ProxyUsbResult, UsbInResult, UsbSpeed};
+
+const PROXY_PORT: u8 = Machine::UHCI_WEBUSB_ROOT_PORT;
+
+fn uhci_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_uhci: true,
+        // Keep this test minimal/deterministic.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn descriptors() -> ProxyUsbDescriptors {
+    ProxyUsbDescriptors {
+        speed: UsbSpeed::Full,
+        device: vec![
+            18, 0x01, 0x10, 0x01, 0xff, 0x00, 0x00, 64, 0x34, 0x12, 0x78, 0x56, 0x00, 0x01, 0, 0,
+            0, 1,
+        ],
+        configuration: vec![9, 0x02, 9, 0, 0, 1, 0, 0x80, 50],
+        strings: Vec::new(),
+    }
+}
+
+fn proxy_bulk_in(m: &Machine, max_len: usize) -> UsbInResult {
+    let uhci = m.uhci().expect("UHCI should be enabled");
+    let mut uhci = uhci.borrow_mut();
+    let mut dev = uhci
+        .controller_mut()
+        .hub_mut()
+        .port_device_mut(usize::from(PROXY_PORT))
+        .expect("proxy device should be attached");
+    dev.model_mut().handle_in_transfer(0x81, max_len)
+}
+
+#[test]
+fn usb_proxy_forwards_transfers_and_naks_until_completion() {
+    let mut m = uhci_machine();
+    let handle = m.usb_proxy_attach(PROXY_PORT, descriptors()).unwrap();
+    assert_eq!(m.usb_proxy_handles(), vec![(handle, PROXY_PORT)]);
+    assert_eq!(m.usb_proxy_descriptors(handle), Some(descriptors()));
+
+    assert_eq!(proxy_bulk_in(&m, 16), UsbInResult::Nak);
+    let reqs = m.usb_proxy_take_requests(handle);
+    assert_eq!(reqs.len(), 1);
+    let UsbHostAction::BulkIn { id, endpoint, .. } = reqs[0] else {
+        panic!("expected BulkIn, got {:?}", reqs[0]);
+    };
+    assert_eq!(endpoint, 0x81);
+
+    // Still outstanding: the endpoint keeps NAKing and the machine keeps running.
+    m.tick_platform(1_000_000);
+    assert_eq!(proxy_bulk_in(&m, 16), UsbInResult::Nak);
+    assert!(m.usb_proxy_take_requests(handle).is_empty());
+
+    assert!(m.usb_proxy_complete(handle, id, ProxyUsbResult::Data(vec![1, 2, 3])));
+    assert!(!m.usb_proxy_complete(handle, id, ProxyUsbResult::Data(vec![1, 2, 3])));
+    assert!(!m.usb_proxy_complete(ProxyHandle(handle.0 + 1), id, ProxyUsbResult::Stall));
+    assert_eq!(proxy_bulk_in(&m, 16), UsbInResult::Data(vec![1, 2, 3]));
+}
+
+#[test]
+fn usb_proxy_detach_removes_device_and_ignores_late_completions() {
+    let mut m = uhci_machine();
+    let handle = m.usb_proxy_attach(PROXY_PORT, descriptors()).unwrap();
+
+    assert_eq!(proxy_bulk_in(&m, 16), UsbInResult::Nak);
+    let reqs = m.usb_proxy_take_requests(handle);
+    let UsbHostAction::BulkIn { id, .. } = reqs[0] else {
+        panic!("expected BulkIn, got {:?}", reqs[0]);
+    };
+
+    m.usb_proxy_detach(handle).unwrap();
+    assert!(m.usb_proxy_handles().is_empty());
+    assert!(!m.usb_proxy_complete(handle, id, ProxyUsbResult::Data(vec![1])));
+    assert!(m.usb_proxy_detach(handle).is_err());
+
+    let uhci = m.uhci().unwrap();
+    assert!(uhci
+        .borrow()
+        .controller()
+        .hub()
+        .port_device(usize::from(PROXY_PORT))
+        .is_none());
+}
+
+#[test]
+fn usb_proxy_attach_rejects_ports_without_usb2_controller() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(m.usb_proxy_attach(0, descriptors()).is_err());
+
+    let mut m = uhci_machine();
+    assert!(m.usb_proxy_attach(2, descriptors()).is_err());
+}
+
+#[test]
+fn usb_proxy_snapshot_records_device_but_not_inflight_transfers() {
+    let mut m = uhci_machine();
+    let handle = m.usb_proxy_attach(PROXY_PORT, descriptors()).unwrap();
+    assert_eq!(proxy_bulk_in(&m, 16), UsbInResult::Nak);
+    assert_eq!(m.usb_proxy_take_requests(handle).len(), 1);
+
+    let snapshot = m.take_snapshot_full().unwrap();
+
+    // Restoring into a fresh machine reconstructs the proxy from the snapshot so the host knows
+    // which device to re-bind.
+    let mut restored = uhci_machine();
+    restored.restore_snapshot_bytes(&snapshot).unwrap();
+    assert_eq!(restored.usb_proxy_handles(), vec![(handle, PROXY_PORT)]);
+    assert_eq!(restored.usb_proxy_descriptors(handle), Some(descriptors()));
+
+    // The in-flight transfer was not snapshotted; the guest's retry emits a fresh request.
+    assert!(restored.usb_proxy_take_requests(handle).is_empty());
+    assert_eq!(proxy_bulk_in(&restored, 16), UsbInResult::Nak);
+    assert_eq!(restored.usb_proxy_take_requests(handle).len(), 1);
+
+    // New proxies never reuse a restored handle.
+    let second = restored.usb_proxy_attach(0, descriptors()).unwrap();
+    assert_ne!(second, handle);
+}
@@ -0,0 +1,566 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{
+    Machine, MachineConfig, MachineError, SharedDirEntry, SharedFileStat, SharedFolderBackend,
+    SharedFolderError, SharedOpenMode,
+};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use pretty_assertions::assert_eq;
+
+const RLERROR: u8 = 7;
+const TLOPEN: u8 = 12;
+const TLCREATE: u8 = 14;
+const TREADDIR: u8 = 40;
+const TVERSION: u8 = 100;
+const TATTACH: u8 = 104;
+const TWALK: u8 = 110;
+const TREAD: u8 = 116;
+const TWRITE: u8 = 118;
+const TCLUNK: u8 = 120;
+
+const ENOENT: u32 = 2;
+const EBADF: u32 = 9;
+const EINVAL: u32 = 22;
+const EROFS: u32 = 30;
+
+const O_RDONLY: u32 = 0;
+const O_RDWR: u32 = 2;
+
+const DESC: u64 = 0x10000;
+const AVAIL: u64 = 0x11000;
+const USED: u64 = 0x12000;
+const REQ_BUF: u64 = 0x20000;
+const RESP_BUF: u64 = 0x30000;
+const RESP_LEN: u32 = 0x2000;
+
+/// `SharedFolderBackend` over a host directory.
+struct DirBackend {
+    root: PathBuf,
+}
+
+impl DirBackend {
+    fn host_path(&self, path: &str) -> PathBuf {
+        if path.is_empty() {
+            self.root.clone()
+        } else {
+            self.root.join(path)
+        }
+    }
+}
+
+fn map_err(err: std::io::Error) -> SharedFolderError {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => SharedFolderError::NotFound,
+        std::io::ErrorKind::AlreadyExists => SharedFolderError::AlreadyExists,
+        std::io::ErrorKind::PermissionDenied => SharedFolderError::PermissionDenied,
+        _ => SharedFolderError::Io,
+    }
+}
+
+impl SharedFolderBackend for DirBackend {
+    fn open(&mut self, path: &str, mode: SharedOpenMode) -> Result<(), SharedFolderError> {
+        fs::OpenOptions::new()
+            .read(true)
+            .write(mode.write || mode.truncate)
+            .truncate(mode.truncate)
+            .open(self.host_path(path))
+            .map(drop)
+            .map_err(map_err)
+    }
+
+    fn read(
+        &mut self,
+        path: &str,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, SharedFolderError> {
+        let mut file = fs::File::open(self.host_path(path)).map_err(map_err)?;
+        file.seek(SeekFrom::Start(offset)).map_err(map_err)?;
+        file.read(buf).map_err(map_err)
+    }
+
+    fn write(&mut self, path: &str, offset: u64, data: &[u8]) -> Result<usize, SharedFolderError> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(self.host_path(path))
+            .map_err(map_err)?;
+        file.seek(SeekFrom::Start(offset)).map_err(map_err)?;
+        file.write(data).map_err(map_err)
+    }
+
+    fn readdir(&mut self, path: &str) -> Result<Vec<SharedDirEntry>, SharedFolderError> {
+        let mut entries = fs::read_dir(self.host_path(path))
+            .map_err(map_err)?
+            .map(|entry| {
+                let entry = entry.map_err(map_err)?;
+                Ok(SharedDirEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: entry.file_type().map_err(map_err)?.is_dir(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn stat(&mut self, path: &str) -> Result<SharedFileStat, SharedFolderError> {
+        let meta = fs::metadata(self.host_path(path)).map_err(map_err)?;
+        Ok(SharedFileStat {
+            is_dir: meta.is_dir(),
+            size: meta.len(),
+            mtime_ns: 0,
+        })
+    }
+
+    fn create(&mut self, path: &str, is_dir: bool) -> Result<(), SharedFolderError> {
+        let path = self.host_path(path);
+        if is_dir {
+            fs::create_dir(path).map_err(map_err)
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map(drop)
+                .map_err(map_err)
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), SharedFolderError> {
+        let path = self.host_path(path);
+        if path.is_dir() {
+            fs::remove_dir(path).map_err(map_err)
+        } else {
+            fs::remove_file(path).map_err(map_err)
+        }
+    }
+}
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+fn machine_cfg(read_only: bool) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_virtio_9p: true,
+        virtio_9p_read_only: read_only,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn share_dir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("hello.txt"), b"hello from the host").unwrap();
+    fs::create_dir(dir.path().join("dir")).unwrap();
+    fs::write(dir.path().join("dir/nested.txt"), b"nested").unwrap();
+    dir
+}
+
+fn new_machine(root: &Path, read_only: bool) -> Machine {
+    let mut m = Machine::new(machine_cfg(read_only)).unwrap();
+    assert!(m.set_shared_folder_backend(Box::new(DirBackend {
+        root: root.to_path_buf(),
+    })));
+    m
+}
+
+/// Minimal guest driver: one request queue, one in-flight request at a time.
+struct Driver {
+    notify: u64,
+    next_tag: u16,
+}
+
+impl Driver {
+    fn init(m: &mut Machine) -> Self {
+        let bdf = profile::VIRTIO_9P.bdf;
+        let bar0 = m.pci_bar_base(bdf, profile::VIRTIO_BAR0_INDEX).unwrap();
+        assert_ne!(bar0, 0);
+
+        // Enable PCI BAR0 decoding + bus mastering.
+        let mut cmd = cfg_read(m, bdf, 0x04, 2) as u16;
+        cmd |= 0x0006; // MEM + BUSMASTER
+        cfg_write(m, bdf, 0x04, 2, u32::from(cmd));
+
+        let common = bar0;
+        let notify = bar0 + u64::from(profile::VIRTIO_NOTIFY_CFG_BAR0_OFFSET);
+
+        m.write_physical_u8(common + 0x14, VIRTIO_STATUS_ACKNOWLEDGE);
+        m.write_physical_u8(
+            common + 0x14,
+            VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER,
+        );
+        for sel in 0..2 {
+            m.write_physical_u32(common, sel);
+            let features = m.read_physical_u32(common + 0x04);
+            m.write_physical_u32(common + 0x08, sel);
+            m.write_physical_u32(common + 0x0c, features);
+        }
+        m.write_physical_u8(
+            common + 0x14,
+            VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_FEATURES_OK,
+        );
+
+        m.write_physical_u16(common + 0x16, 0); // queue_select
+        m.write_physical_u64(common + 0x20, DESC);
+        m.write_physical_u64(common + 0x28, AVAIL);
+        m.write_physical_u64(common + 0x30, USED);
+        m.write_physical_u16(common + 0x1c, 1); // queue_enable
+        m.write_physical_u16(AVAIL, 0);
+        m.write_physical_u16(AVAIL + 2, 0);
+        m.write_physical_u16(USED, 0);
+        m.write_physical_u16(USED + 2, 0);
+
+        m.write_physical_u8(
+            common + 0x14,
+            VIRTIO_STATUS_ACKNOWLEDGE
+                | VIRTIO_STATUS_DRIVER
+                | VIRTIO_STATUS_FEATURES_OK
+                | VIRTIO_STATUS_DRIVER_OK,
+        );
+
+        Self {
+            notify,
+            next_tag: 1,
+        }
+    }
+
+    /// Send one T-message and return `(type, body)` of the R-message.
+    fn call(&mut self, m: &mut Machine, ty: u8, body: &[u8]) -> (u8, Vec<u8>) {
+        let tag = self.next_tag;
+        self.next_tag = self.next_tag.wrapping_add(1);
+
+        let mut req = Vec::new();
+        req.extend_from_slice(&((7 + body.len()) as u32).to_le_bytes());
+        req.push(ty);
+        req.extend_from_slice(&tag.to_le_bytes());
+        req.extend_from_slice(body);
+        m.write_physical(REQ_BUF, &req);
+
+        // desc 0: request (device-readable) -> desc 1: reply (device-writable).
+        m.write_physical_u64(DESC, REQ_BUF);
+        m.write_physical_u32(DESC + 8, req.len() as u32);
+        m.write_physical_u16(DESC + 12, 0x1); // NEXT
+        m.write_physical_u16(DESC + 14, 1);
+        m.write_physical_u64(DESC + 16, RESP_BUF);
+        m.write_physical_u32(DESC + 24, RESP_LEN);
+        m.write_physical_u16(DESC + 28, 0x2); // WRITE
+        m.write_physical_u16(DESC + 30, 0);
+
+        let avail_idx = m.read_physical_u16(AVAIL + 2);
+        m.write_physical_u16(AVAIL + 4 + u64::from(avail_idx % 128) * 2, 0);
+        m.write_physical_u16(AVAIL + 2, avail_idx.wrapping_add(1));
+
+        m.write_physical_u16(self.notify, 0);
+        m.process_virtio_9p();
+        let used_idx = m.read_physical_u16(USED + 2);
+        assert_eq!(used_idx, avail_idx.wrapping_add(1), "request not completed");
+
+        let elem = USED + 4 + u64::from(avail_idx % 128) * 8;
+        let len = m.read_physical_u32(elem + 4) as usize;
+        let reply = m.read_physical_bytes(RESP_BUF, len);
+        assert!(len >= 7);
+        assert_eq!(
+            u32::from_le_bytes(reply[0..4].try_into().unwrap()) as usize,
+            len
+        );
+        assert_eq!(u16::from_le_bytes([reply[5], reply[6]]), tag);
+        (reply[4], reply[7..].to_vec())
+    }
+
+    /// Like [`Driver::call`], but expects a successful reply.
+    fn ok(&mut self, m: &mut Machine, ty: u8, body: &[u8]) -> Vec<u8> {
+        let (rty, body) = self.call(m, ty, body);
+        assert_eq!(rty, ty + 1, "unexpected reply {rty} (body {body:?})");
+        body
+    }
+
+    /// Like [`Driver::call`], but expects `Rlerror` and returns its errno.
+    fn err(&mut self, m: &mut Machine, ty: u8, body: &[u8]) -> u32 {
+        let (rty, body) = self.call(m, ty, body);
+        assert_eq!(rty, RLERROR);
+        u32::from_le_bytes(body[0..4].try_into().unwrap())
+    }
+
+    fn version_and_attach(&mut self, m: &mut Machine, root_fid: u32) {
+        let reply = self.ok(m, TVERSION, &msg().u32(8192).str("9P2000.L").0);
+        assert_eq!(reply, msg().u32(8192).str("9P2000.L").0);
+
+        let qid = self.ok(
+            m,
+            TATTACH,
+            &msg().u32(root_fid).u32(!0).str("root").str("").u32(0).0,
+        );
+        assert_eq!(qid.len(), 13);
+        assert_eq!(qid[0], 0x80, "root qid must be a directory");
+    }
+}
+
+/// 9P message body builder.
+struct Msg(Vec<u8>);
+
+fn msg() -> Msg {
+    Msg(Vec::new())
+}
+
+impl Msg {
+    fn u16(mut self, v: u16) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u32(mut self, v: u32) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u64(mut self, v: u64) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn str(self, s: &str) -> Self {
+        let mut this = self.u16(s.len() as u16);
+        this.0.extend_from_slice(s.as_bytes());
+        this
+    }
+
+    fn bytes(mut self, b: &[u8]) -> Self {
+        self.0.extend_from_slice(b);
+        self
+    }
+}
+
+fn walk(names: &[&str], fid: u32, newfid: u32) -> Vec<u8> {
+    let mut m = msg().u32(fid).u32(newfid).u16(names.len() as u16);
+    for name in names {
+        m = m.str(name);
+    }
+    m.0
+}
+
+fn rread_data(reply: &[u8]) -> &[u8] {
+    let count = u32::from_le_bytes(reply[0..4].try_into().unwrap()) as usize;
+    &reply[4..4 + count]
+}
+
+#[test]
+fn virtio_9p_requires_pc_platform() {
+    let err = Machine::new(MachineConfig {
+        enable_pc_platform: false,
+        ..machine_cfg(false)
+    })
+    .err()
+    .unwrap();
+    assert!(matches!(err, MachineError::Virtio9pRequiresPcPlatform));
+}
+
+#[test]
+fn virtio_9p_config_exposes_mount_tag() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    Driver::init(&mut m);
+    let bar0 = m
+        .pci_bar_base(profile::VIRTIO_9P.bdf, profile::VIRTIO_BAR0_INDEX)
+        .unwrap();
+    let device_cfg = bar0 + u64::from(profile::VIRTIO_DEVICE_CFG_BAR0_OFFSET);
+    assert_eq!(m.read_physical_u16(device_cfg), 4);
+    assert_eq!(m.read_physical_bytes(device_cfg + 2, 4), b"aero");
+}
+
+#[test]
+fn virtio_9p_version_attach_walk_and_read() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+
+    // Multi-component walk returns one qid per name.
+    let reply = drv.ok(&mut m, TWALK, &walk(&["dir", "nested.txt"], 0, 1));
+    assert_eq!(u16::from_le_bytes([reply[0], reply[1]]), 2);
+    assert_eq!(reply.len(), 2 + 2 * 13);
+    assert_eq!(reply[2], 0x80);
+    assert_eq!(reply[2 + 13], 0x00);
+
+    // Reads need an open fid.
+    assert_eq!(
+        drv.err(&mut m, TREAD, &msg().u32(1).u64(0).u32(64).0),
+        EBADF
+    );
+    drv.ok(&mut m, TLOPEN, &msg().u32(1).u32(O_RDONLY).0);
+    let reply = drv.ok(&mut m, TREAD, &msg().u32(1).u64(0).u32(64).0);
+    assert_eq!(rread_data(&reply), b"nested");
+    let reply = drv.ok(&mut m, TREAD, &msg().u32(1).u64(6).u32(64).0);
+    assert_eq!(rread_data(&reply), b"");
+
+    // Walk from the root to a top-level file and read at an offset.
+    drv.ok(&mut m, TWALK, &walk(&["hello.txt"], 0, 2));
+    drv.ok(&mut m, TLOPEN, &msg().u32(2).u32(O_RDONLY).0);
+    let reply = drv.ok(&mut m, TREAD, &msg().u32(2).u64(6).u32(4).0);
+    assert_eq!(rread_data(&reply), b"from");
+
+    drv.ok(&mut m, TCLUNK, &msg().u32(2).0);
+    assert_eq!(drv.err(&mut m, TCLUNK, &msg().u32(2).0), EBADF);
+}
+
+#[test]
+fn virtio_9p_walk_errors_and_root_confinement() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+
+    assert_eq!(drv.err(&mut m, TWALK, &walk(&["missing"], 0, 1)), ENOENT);
+
+    // A failure after the first name returns the partial walk and leaves newfid unset.
+    let reply = drv.ok(&mut m, TWALK, &walk(&["dir", "missing"], 0, 1));
+    assert_eq!(u16::from_le_bytes([reply[0], reply[1]]), 1);
+    assert_eq!(drv.err(&mut m, TCLUNK, &msg().u32(1).0), EBADF);
+
+    // `..` cannot escape the shared folder.
+    drv.ok(&mut m, TWALK, &walk(&["..", "..", "hello.txt"], 0, 3));
+    drv.ok(&mut m, TLOPEN, &msg().u32(3).u32(O_RDONLY).0);
+    let reply = drv.ok(&mut m, TREAD, &msg().u32(3).u64(0).u32(5).0);
+    assert_eq!(rread_data(&reply), b"hello");
+
+    // Path separators are not valid inside a name.
+    assert_eq!(
+        drv.err(&mut m, TWALK, &walk(&["dir/nested.txt"], 0, 4)),
+        EINVAL
+    );
+}
+
+#[test]
+fn virtio_9p_readdir_lists_entries() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+
+    drv.ok(&mut m, TWALK, &walk(&[], 0, 1));
+    drv.ok(&mut m, TLOPEN, &msg().u32(1).u32(O_RDONLY).0);
+    let reply = drv.ok(&mut m, TREADDIR, &msg().u32(1).u64(0).u32(4096).0);
+    let data = rread_data(&reply);
+
+    // qid[13] offset[8] type[1] name[s]
+    let mut names = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let ty = data[pos + 21];
+        let len = usize::from(u16::from_le_bytes([data[pos + 22], data[pos + 23]]));
+        let name = String::from_utf8(data[pos + 24..pos + 24 + len].to_vec()).unwrap();
+        names.push((name, ty));
+        pos += 24 + len;
+    }
+    assert_eq!(
+        names,
+        vec![
+            (".".to_string(), 4),
+            ("..".to_string(), 4),
+            ("dir".to_string(), 4),
+            ("hello.txt".to_string(), 8),
+        ]
+    );
+
+    // Resuming after the last entry yields nothing.
+    let reply = drv.ok(&mut m, TREADDIR, &msg().u32(1).u64(4).u32(4096).0);
+    assert!(rread_data(&reply).is_empty());
+}
+
+#[test]
+fn virtio_9p_create_and_write_reach_the_host_folder() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+
+    drv.ok(&mut m, TWALK, &walk(&["dir"], 0, 1));
+    drv.ok(
+        &mut m,
+        TLCREATE,
+        &msg().u32(1).str("new.txt").u32(O_RDWR).u32(0o644).u32(0).0,
+    );
+    let reply = drv.ok(
+        &mut m,
+        TWRITE,
+        &msg().u32(1).u64(0).u32(5).bytes(b"guest").0,
+    );
+    assert_eq!(reply, 5u32.to_le_bytes());
+    assert_eq!(fs::read(dir.path().join("dir/new.txt")).unwrap(), b"guest");
+}
+
+#[test]
+fn virtio_9p_read_only_rejects_modifications() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), true);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+
+    drv.ok(&mut m, TWALK, &walk(&["hello.txt"], 0, 1));
+    assert_eq!(drv.err(&mut m, TLOPEN, &msg().u32(1).u32(O_RDWR).0), EROFS);
+    drv.ok(&mut m, TLOPEN, &msg().u32(1).u32(O_RDONLY).0);
+    assert_eq!(
+        drv.err(&mut m, TWRITE, &msg().u32(1).u64(0).u32(1).bytes(b"x").0),
+        EBADF
+    );
+
+    drv.ok(&mut m, TWALK, &walk(&[], 0, 2));
+    assert_eq!(
+        drv.err(
+            &mut m,
+            TLCREATE,
+            &msg().u32(2).str("new.txt").u32(O_RDWR).u32(0o644).u32(0).0,
+        ),
+        EROFS
+    );
+    assert!(!dir.path().join("new.txt").exists());
+    assert_eq!(
+        fs::read(dir.path().join("hello.txt")).unwrap(),
+        b"hello from the host"
+    );
+}
+
+#[test]
+fn virtio_9p_open_fids_survive_snapshot_restore() {
+    let dir = share_dir();
+    let mut m = new_machine(dir.path(), false);
+    let mut drv = Driver::init(&mut m);
+    drv.version_and_attach(&mut m, 0);
+    drv.ok(&mut m, TWALK, &walk(&["hello.txt"], 0, 1));
+    drv.ok(&mut m, TLOPEN, &msg().u32(1).u32(O_RDONLY).0);
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = new_machine(dir.path(), false);
+    restored.restore_snapshot_bytes(&snap).unwrap();
+
+    let reply = drv.ok(&mut restored, TREAD, &msg().u32(1).u64(0).u32(5).0);
+    assert_eq!(rread_data(&reply), b"hello");
+}
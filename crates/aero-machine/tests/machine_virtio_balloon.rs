@@ -0,0 +1,196 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{Machine, MachineConfig, MachineError};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use memory::SparseMemory;
+use pretty_assertions::assert_eq;
+
+const RAM_SIZE: u64 = 16 * 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Guest RAM region the test fills and then balloons away.
+const BALLOON_BASE: u64 = 0x40_0000;
+const BALLOON_LEN: u64 = 0x40_0000;
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+fn bar0_base(m: &mut Machine, bdf: PciBdf) -> u64 {
+    let bar0_lo = cfg_read(m, bdf, 0x10, 4);
+    let bar0_hi = cfg_read(m, bdf, 0x14, 4);
+    (u64::from(bar0_hi) << 32) | u64::from(bar0_lo & 0xFFFF_FFF0)
+}
+
+fn machine_cfg() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: RAM_SIZE,
+        enable_pc_platform: true,
+        enable_virtio_balloon: true,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn sparse_machine() -> Machine {
+    Machine::new_with_guest_memory(
+        machine_cfg(),
+        Box::new(SparseMemory::with_chunk_size(RAM_SIZE, CHUNK_SIZE).unwrap()),
+    )
+    .unwrap()
+}
+
+#[test]
+fn virtio_balloon_requires_pc_platform() {
+    let err = Machine::new(MachineConfig {
+        enable_pc_platform: false,
+        ..machine_cfg()
+    })
+    .err()
+    .unwrap();
+    assert!(matches!(err, MachineError::VirtioBalloonRequiresPcPlatform));
+}
+
+#[test]
+fn virtio_balloon_inflate_releases_sparse_ram_and_survives_snapshot() {
+    let mut m = sparse_machine();
+    m.write_physical(BALLOON_BASE, &vec![0xAA; BALLOON_LEN as usize]);
+
+    assert!(m.balloon_set_target(BALLOON_LEN));
+
+    let bdf = profile::VIRTIO_BALLOON.bdf;
+    let bar0 = bar0_base(&mut m, bdf);
+    assert_ne!(bar0, 0);
+
+    // Enable PCI BAR0 decoding + bus mastering.
+    let mut cmd = cfg_read(&mut m, bdf, 0x04, 2) as u16;
+    cmd |= 0x0006; // MEM + BUSMASTER
+    cfg_write(&mut m, bdf, 0x04, 2, u32::from(cmd));
+
+    let common = bar0;
+    let notify = bar0 + u64::from(profile::VIRTIO_NOTIFY_CFG_BAR0_OFFSET);
+    let device_cfg = bar0 + u64::from(profile::VIRTIO_DEVICE_CFG_BAR0_OFFSET);
+
+    // Feature negotiation (modern virtio-pci).
+    m.write_physical_u8(common + 0x14, VIRTIO_STATUS_ACKNOWLEDGE);
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER,
+    );
+    for sel in 0..2 {
+        m.write_physical_u32(common, sel);
+        let features = m.read_physical_u32(common + 0x04);
+        m.write_physical_u32(common + 0x08, sel);
+        m.write_physical_u32(common + 0x0c, features);
+    }
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_FEATURES_OK,
+    );
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE
+            | VIRTIO_STATUS_DRIVER
+            | VIRTIO_STATUS_FEATURES_OK
+            | VIRTIO_STATUS_DRIVER_OK,
+    );
+
+    // The driver reads the host target from device config.
+    let target_pages = (BALLOON_LEN / 4096) as u32;
+    assert_eq!(m.read_physical_u32(device_cfg), target_pages);
+
+    // Configure inflate queue 0.
+    let desc = 0x10000u64;
+    let avail = 0x11000u64;
+    let used = 0x12000u64;
+    let pfns = 0x13000u64;
+
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, desc);
+    m.write_physical_u64(common + 0x28, avail);
+    m.write_physical_u64(common + 0x30, used);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+
+    // One descriptor carrying every PFN in the ballooned region.
+    let first_pfn = (BALLOON_BASE / 4096) as u32;
+    let pfn_bytes: Vec<u8> = (first_pfn..first_pfn + target_pages)
+        .flat_map(u32::to_le_bytes)
+        .collect();
+    m.write_physical(pfns, &pfn_bytes);
+    m.write_physical_u64(desc, pfns);
+    m.write_physical_u32(desc + 8, pfn_bytes.len() as u32);
+    m.write_physical_u16(desc + 12, 0);
+    m.write_physical_u16(desc + 14, 0);
+    m.write_physical_u16(avail, 0);
+    m.write_physical_u16(avail + 2, 1);
+    m.write_physical_u16(avail + 4, 0);
+    m.write_physical_u16(used, 0);
+    m.write_physical_u16(used + 2, 0);
+
+    let resident_before = m.balloon_stats().unwrap().resident_ram_bytes;
+    assert!(resident_before >= BALLOON_LEN);
+
+    m.write_physical_u16(notify, 0);
+    m.process_virtio_balloon();
+    assert_eq!(m.read_physical_u16(used + 2), 1);
+
+    // The driver reports progress via `actual`.
+    m.write_physical_u32(device_cfg + 4, target_pages);
+
+    let stats = m.balloon_stats().unwrap();
+    assert_eq!(stats.target_bytes, BALLOON_LEN);
+    assert_eq!(stats.actual_bytes, BALLOON_LEN);
+    assert_eq!(stats.guest, None);
+    assert!(
+        stats.resident_ram_bytes + BALLOON_LEN <= resident_before,
+        "resident RAM did not drop: before={resident_before} after={}",
+        stats.resident_ram_bytes
+    );
+
+    // Ballooned pages read back as zero.
+    assert!(m
+        .read_physical_bytes(BALLOON_BASE, BALLOON_LEN as usize)
+        .iter()
+        .all(|&b| b == 0));
+
+    // Snapshots store ballooned pages as zero pages, and restoring them does not re-commit RAM.
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = sparse_machine();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    let restored_stats = restored.balloon_stats().unwrap();
+    assert_eq!(restored_stats.target_bytes, BALLOON_LEN);
+    assert_eq!(restored_stats.actual_bytes, BALLOON_LEN);
+    assert!(restored_stats.resident_ram_bytes + BALLOON_LEN <= resident_before);
+
+    // Deflated pages are repopulated on demand.
+    restored.write_physical_u32(BALLOON_BASE, 0x1234_5678);
+    assert_eq!(restored.read_physical_u32(BALLOON_BASE), 0x1234_5678);
+
+    // The host target is policy and survives a guest reset; the guest's progress does not.
+    restored.reset();
+    let reset_stats = restored.balloon_stats().unwrap();
+    assert_eq!(reset_stats.target_bytes, BALLOON_LEN);
+    assert_eq!(reset_stats.actual_bytes, 0);
+}
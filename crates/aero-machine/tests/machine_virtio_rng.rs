@@ -0,0 +1,230 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{EntropySource, Machine, MachineConfig, MachineError};
+use aero_virtio::devices::rng::{ChaChaEntropySource, VIRTIO_RNG_DEFAULT_BYTES_PER_TICK};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use pretty_assertions::assert_eq;
+
+const DESC: u64 = 0x10000;
+const AVAIL: u64 = 0x11000;
+const USED: u64 = 0x12000;
+const BUFS: u64 = 0x20000;
+const BUF_STRIDE: u64 = 0x1000;
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+fn machine_cfg(seed: u64) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_virtio_rng: true,
+        rng_seed: seed,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+/// Bring the device to DRIVER_OK with its request queue configured; returns the notify address.
+fn init_driver(m: &mut Machine) -> u64 {
+    let bdf = profile::VIRTIO_RNG.bdf;
+    let bar0 = m.pci_bar_base(bdf, profile::VIRTIO_BAR0_INDEX).unwrap();
+    assert_ne!(bar0, 0);
+
+    let cmd = cfg_read(m, bdf, 0x04, 2) | 0x0006; // MEM + BUSMASTER
+    cfg_write(m, bdf, 0x04, 2, cmd);
+
+    let common = bar0;
+    let mut status = VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER;
+    m.write_physical_u8(common + 0x14, status);
+    for sel in 0..2 {
+        m.write_physical_u32(common, sel);
+        let features = m.read_physical_u32(common + 0x04);
+        m.write_physical_u32(common + 0x08, sel);
+        m.write_physical_u32(common + 0x0c, features);
+    }
+    status |= VIRTIO_STATUS_FEATURES_OK;
+    m.write_physical_u8(common + 0x14, status);
+
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, DESC);
+    m.write_physical_u64(common + 0x28, AVAIL);
+    m.write_physical_u64(common + 0x30, USED);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+    m.write_physical_u16(AVAIL, 0);
+    m.write_physical_u16(AVAIL + 2, 0);
+    m.write_physical_u16(USED, 0);
+    m.write_physical_u16(USED + 2, 0);
+
+    m.write_physical_u8(common + 0x14, status | VIRTIO_STATUS_DRIVER_OK);
+    bar0 + u64::from(profile::VIRTIO_NOTIFY_CFG_BAR0_OFFSET)
+}
+
+/// Post one write-only buffer of `len` bytes per entry (descriptor `i` at `BUFS + i * BUF_STRIDE`)
+/// and kick the queue, without processing it.
+fn post(m: &mut Machine, notify: u64, lens: &[u32]) {
+    let start = m.read_physical_u16(AVAIL + 2);
+    for (i, &len) in lens.iter().enumerate() {
+        let idx = start + i as u16;
+        let desc = DESC + u64::from(idx) * 16;
+        m.write_physical_u64(desc, BUFS + u64::from(idx) * BUF_STRIDE);
+        m.write_physical_u32(desc + 8, len);
+        m.write_physical_u16(desc + 12, 0x2); // WRITE
+        m.write_physical_u16(desc + 14, 0);
+        m.write_physical_u16(AVAIL + 4 + u64::from(idx) * 2, idx);
+    }
+    m.write_physical_u16(AVAIL + 2, start + lens.len() as u16);
+    m.write_physical_u16(notify, 0);
+}
+
+fn used_idx(m: &mut Machine) -> u16 {
+    m.read_physical_u16(USED + 2)
+}
+
+/// Bytes written into the buffer of used ring entry `i`.
+fn used_bytes(m: &mut Machine, i: u16) -> Vec<u8> {
+    let elem = USED + 4 + u64::from(i) * 8;
+    let head = m.read_physical_u32(elem);
+    let len = m.read_physical_u32(elem + 4) as usize;
+    m.read_physical_bytes(BUFS + u64::from(head) * BUF_STRIDE, len)
+}
+
+fn stream(seed: u64, skip: usize, len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; skip + len];
+    ChaChaEntropySource::new(seed).fill_bytes(&mut out);
+    out.split_off(skip)
+}
+
+#[test]
+fn virtio_rng_requires_pc_platform() {
+    let err = Machine::new(MachineConfig {
+        enable_pc_platform: false,
+        ..machine_cfg(0)
+    })
+    .err()
+    .unwrap();
+    assert!(matches!(err, MachineError::VirtioRngRequiresPcPlatform));
+}
+
+#[test]
+fn virtio_rng_serves_the_seeded_stream() {
+    let mut m = Machine::new(machine_cfg(7)).unwrap();
+    let notify = init_driver(&mut m);
+
+    post(&mut m, notify, &[32, 64]);
+    m.process_virtio_rng();
+    assert_eq!(used_idx(&mut m), 2);
+    assert_eq!(used_bytes(&mut m, 0), stream(7, 0, 32));
+    assert_eq!(used_bytes(&mut m, 1), stream(7, 32, 64));
+
+    // Another seed yields another stream.
+    let mut other = Machine::new(machine_cfg(8)).unwrap();
+    let notify = init_driver(&mut other);
+    post(&mut other, notify, &[32]);
+    other.process_virtio_rng();
+    assert_ne!(used_bytes(&mut other, 0), stream(7, 0, 32));
+}
+
+#[test]
+fn virtio_rng_hands_out_a_bounded_number_of_bytes_per_tick() {
+    let mut m = Machine::new(machine_cfg(1)).unwrap();
+    let notify = init_driver(&mut m);
+    let budget = VIRTIO_RNG_DEFAULT_BYTES_PER_TICK as u32;
+
+    // A greedy driver posts four budget-sized buffers at once.
+    post(&mut m, notify, &[budget; 4]);
+    for tick in 1..=4 {
+        m.process_virtio_rng();
+        assert_eq!(used_idx(&mut m), tick);
+    }
+    assert_eq!(
+        used_bytes(&mut m, 3),
+        stream(1, 3 * budget as usize, budget as usize)
+    );
+}
+
+#[test]
+fn virtio_rng_stream_is_bit_exact_across_snapshot_restore() {
+    let mut m = Machine::new(machine_cfg(0xfeed)).unwrap();
+    let notify = init_driver(&mut m);
+    let budget = VIRTIO_RNG_DEFAULT_BYTES_PER_TICK as u32;
+
+    post(&mut m, notify, &[100]);
+    m.process_virtio_rng();
+    // Leave a buffer held for lack of budget at snapshot time.
+    post(&mut m, notify, &[budget, 16]);
+    m.process_virtio_rng();
+    assert_eq!(used_idx(&mut m), 2);
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = Machine::new(machine_cfg(0xfeed)).unwrap();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+
+    for machine in [&mut m, &mut restored] {
+        machine.process_virtio_rng();
+        assert_eq!(used_idx(machine), 3);
+    }
+    assert_eq!(used_bytes(&mut m, 2), used_bytes(&mut restored, 2));
+    assert_eq!(
+        used_bytes(&mut restored, 2),
+        stream(0xfeed, 100 + budget as usize, 16)
+    );
+}
+
+struct CountingSource(u8);
+
+impl EntropySource for CountingSource {
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            *b = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+}
+
+#[test]
+fn virtio_rng_host_source_survives_reset() {
+    let mut m = Machine::new(machine_cfg(3)).unwrap();
+    assert!(m.set_entropy_source(Box::new(CountingSource(10))));
+    let notify = init_driver(&mut m);
+    post(&mut m, notify, &[4]);
+    m.process_virtio_rng();
+    assert_eq!(used_bytes(&mut m, 0), [10, 11, 12, 13]);
+
+    m.reset();
+    let notify = init_driver(&mut m);
+    post(&mut m, notify, &[2]);
+    m.process_virtio_rng();
+    assert_eq!(used_bytes(&mut m, 0), [14, 15]);
+
+    // Machines without the device report that nothing was installed.
+    let mut plain = Machine::new(MachineConfig {
+        enable_virtio_rng: false,
+        ..machine_cfg(3)
+    })
+    .unwrap();
+    assert!(!plain.set_entropy_source(Box::new(CountingSource(0))));
+}
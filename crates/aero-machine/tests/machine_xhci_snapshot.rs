@@ -206,6 +206,10 @@ fn xhci_enabled_restore_without_xhci_payload_leaves_xhci_at_reset_state() {
         .borrow()
         .controller()
         .save_state();
-    let default_state = XhciController::new().save_state();
+    let default_state = XhciController::with_port_layout(
+        Machine::XHCI_USB2_PORT_COUNT,
+        Machine::XHCI_USB3_PORT_COUNT,
+    )
+    .save_state();
     assert_eq!(xhci_state, default_state);
 }
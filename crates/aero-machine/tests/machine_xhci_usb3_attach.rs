@@ -0,0 +1,326 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::a20_gate::A20_GATE_PORT;
+use aero_devices::pci::profile::USB_XHCI_QEMU;
+use aero_devices::usb::xhci::regs;
+use aero_machine::{Machine, MachineConfig};
+use aero_usb::xhci::context::CONTEXT_SIZE;
+use aero_usb::xhci::trb::{CompletionCode, Trb, TrbType, TRB_LEN};
+use aero_usb::{ControlResponse, SetupPacket, UsbDeviceModel, UsbHubAttachError, UsbSpeed};
+use pretty_assertions::assert_eq;
+
+const NS_PER_MS: u64 = 1_000_000;
+
+/// USB 3.0 device descriptor (bcdUSB=0x0300, bMaxPacketSize0=2^9).
+const DEVICE_DESCRIPTOR: [u8; 18] = [
+    0x12, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x09, 0x34, 0x12, 0x78, 0x56, 0x00, 0x01, 0x00, 0x00,
+    0x00, 0x01,
+];
+
+/// Minimal SuperSpeed device that only answers SET_ADDRESS and GET_DESCRIPTOR(Device).
+struct SuperSpeedDevice;
+
+impl UsbDeviceModel for SuperSpeedDevice {
+    fn speed(&self) -> UsbSpeed {
+        UsbSpeed::Super
+    }
+
+    fn handle_control_request(
+        &mut self,
+        setup: SetupPacket,
+        _data_stage: Option<&[u8]>,
+    ) -> ControlResponse {
+        match (setup.bm_request_type, setup.b_request, setup.w_value) {
+            // SET_ADDRESS
+            (0x00, 0x05, _) => ControlResponse::Ack,
+            // GET_DESCRIPTOR(Device)
+            (0x80, 0x06, 0x0100) => {
+                let len = DEVICE_DESCRIPTOR.len().min(usize::from(setup.w_length));
+                ControlResponse::Data(DEVICE_DESCRIPTOR[..len].to_vec())
+            }
+            _ => ControlResponse::Stall,
+        }
+    }
+}
+
+fn minimal_xhci_config() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_xhci: true,
+        // Keep the machine minimal/deterministic for this xHCI test.
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        ..Default::default()
+    }
+}
+
+/// Guest-side driver state: ring addresses plus the event ring consumer index.
+struct Driver {
+    bar0: u64,
+    cmd_ring: u64,
+    cmd_index: u64,
+    event_ring: u64,
+    event_index: u64,
+}
+
+impl Driver {
+    const DCBAA: u64 = 0x10_000;
+    const CMD_RING: u64 = 0x11_000;
+    const ERST: u64 = 0x12_000;
+    const EVENT_RING: u64 = 0x13_000;
+    const EVENT_RING_TRBS: u32 = 64;
+
+    /// Enables MMIO + bus mastering and brings the controller up with one device slot.
+    fn start(m: &mut Machine) -> Self {
+        // Ensure high MMIO addresses decode correctly (avoid A20 aliasing).
+        m.io_write(A20_GATE_PORT, 1, 0x02);
+
+        let bdf = USB_XHCI_QEMU.bdf;
+        {
+            let pci_cfg = m
+                .pci_config_ports()
+                .expect("pc platform should expose pci_cfg");
+            let mut pci_cfg = pci_cfg.borrow_mut();
+            let bus = pci_cfg.bus_mut();
+            let cmd = bus.read_config(bdf, 0x04, 2) as u16;
+            bus.write_config(bdf, 0x04, 2, u32::from(cmd | (1 << 1) | (1 << 2)));
+        }
+        let bar0 = m.pci_bar_base(bdf, 0).expect("xHCI BAR0 should exist");
+
+        m.write_physical(Self::EVENT_RING, &[0u8; TRB_LEN * 64]);
+        m.write_physical_u64(Self::ERST, Self::EVENT_RING);
+        m.write_physical_u32(Self::ERST + 8, Self::EVENT_RING_TRBS);
+        m.write_physical_u32(Self::ERST + 12, 0);
+
+        m.write_physical_u64(bar0 + regs::REG_DCBAAP_LO, Self::DCBAA);
+        m.write_physical_u64(bar0 + regs::REG_CRCR_LO, Self::CMD_RING | 1);
+        m.write_physical_u32(bar0 + regs::REG_CONFIG, 1);
+        m.write_physical_u32(bar0 + regs::REG_INTR0_ERSTSZ, 1);
+        m.write_physical_u64(bar0 + regs::REG_INTR0_ERSTBA_LO, Self::ERST);
+        m.write_physical_u64(bar0 + regs::REG_INTR0_ERDP_LO, Self::EVENT_RING);
+        m.write_physical_u32(bar0 + regs::REG_INTR0_IMAN, regs::IMAN_IE);
+        m.write_physical_u32(bar0 + regs::REG_USBCMD, regs::USBCMD_RUN);
+
+        Self {
+            bar0,
+            cmd_ring: Self::CMD_RING,
+            cmd_index: 0,
+            event_ring: Self::EVENT_RING,
+            event_index: 0,
+        }
+    }
+
+    fn read_portsc(&self, m: &mut Machine, port: u8) -> u32 {
+        m.read_physical_u32(self.bar0 + regs::port::portsc_offset(usize::from(port)))
+    }
+
+    fn ring_doorbell(&self, m: &mut Machine, slot_id: u8, target: u32) {
+        let db = self.bar0 + u64::from(regs::DBOFF_VALUE) + u64::from(slot_id) * 4;
+        m.write_physical_u32(db, target);
+    }
+
+    /// Ticks the platform until the next event TRB is posted, then advances ERDP past it.
+    fn next_event(&mut self, m: &mut Machine) -> Trb {
+        let addr = self.event_ring + self.event_index * TRB_LEN as u64;
+        for _ in 0..50 {
+            let trb = Trb::from_bytes(m.read_physical_bytes(addr, TRB_LEN).try_into().unwrap());
+            if trb.cycle() {
+                self.event_index += 1;
+                let erdp = self.event_ring + self.event_index * TRB_LEN as u64;
+                m.write_physical_u64(self.bar0 + regs::REG_INTR0_ERDP_LO, erdp);
+                return trb;
+            }
+            m.tick_platform(NS_PER_MS);
+        }
+        panic!("timed out waiting for event TRB {}", self.event_index);
+    }
+
+    fn command(&mut self, m: &mut Machine, mut trb: Trb) -> Trb {
+        trb.set_cycle(true);
+        let addr = self.cmd_ring + self.cmd_index * TRB_LEN as u64;
+        m.write_physical(addr, &trb.to_bytes());
+        self.cmd_index += 1;
+        self.ring_doorbell(m, 0, 0);
+
+        let ev = self.next_event(m);
+        assert_eq!(ev.trb_type(), TrbType::CommandCompletionEvent);
+        assert_eq!(ev.parameter & !0x0f, addr);
+        ev
+    }
+}
+
+#[test]
+fn xhci_usb3_attach_address_device_and_control_transfer() {
+    let mut m = Machine::new(minimal_xhci_config()).unwrap();
+    let mut drv = Driver::start(&mut m);
+
+    let port = Machine::XHCI_FIRST_USB3_PORT;
+    m.xhci_attach_device(port, Box::new(SuperSpeedDevice))
+        .unwrap();
+    assert_eq!(m.xhci_attached_ports(), vec![port]);
+
+    // Link training completes on its own; the port comes up enabled at SuperSpeed.
+    let ev = drv.next_event(&mut m);
+    assert_eq!(ev.trb_type(), TrbType::PortStatusChangeEvent);
+    assert_eq!(
+        ((ev.parameter >> regs::PSC_EVENT_PORT_ID_SHIFT) & 0xff) as u8,
+        port + 1
+    );
+    let portsc = drv.read_portsc(&mut m, port);
+    assert_eq!(
+        (portsc & regs::PORTSC_PLS_MASK) >> regs::PORTSC_PLS_SHIFT,
+        u32::from(regs::PLS_U0)
+    );
+    assert_ne!(portsc & regs::PORTSC_CCS, 0);
+    assert_ne!(portsc & regs::PORTSC_PED, 0);
+    assert_eq!(
+        (portsc & regs::PORTSC_PS_MASK) >> regs::PORTSC_PS_SHIFT,
+        u32::from(regs::PSIV_SUPER_SPEED)
+    );
+
+    // Enable Slot.
+    let mut enable_slot = Trb::new(0, 0, 0);
+    enable_slot.set_trb_type(TrbType::EnableSlotCommand);
+    let ev = drv.command(&mut m, enable_slot);
+    assert_eq!(ev.completion_code_raw(), CompletionCode::Success.as_u8());
+    let slot_id = ev.slot_id();
+    assert_eq!(slot_id, 1);
+
+    let dev_ctx = 0x20_000u64;
+    let input_ctx = 0x21_000u64;
+    let ep0_ring = 0x22_000u64;
+    let data_buf = 0x23_000u64;
+    m.write_physical_u64(Driver::DCBAA + u64::from(slot_id) * 8, dev_ctx);
+
+    // Input context: add Slot + EP0; route to the USB3 root port with EP0 MPS=512.
+    m.write_physical_u32(input_ctx, 0);
+    m.write_physical_u32(input_ctx + 0x04, (1 << 0) | (1 << 1));
+    let slot = input_ctx + CONTEXT_SIZE as u64;
+    m.write_physical_u32(slot, (u32::from(regs::PSIV_SUPER_SPEED) << 20) | (1 << 27));
+    m.write_physical_u32(slot + 0x04, u32::from(port + 1) << 16);
+    let ep0 = input_ctx + 2 * CONTEXT_SIZE as u64;
+    m.write_physical_u32(ep0 + 0x04, (4 << 3) | (512 << 16));
+    m.write_physical_u64(ep0 + 0x08, ep0_ring | 1);
+
+    let mut address_device = Trb::new(input_ctx, 0, 0);
+    address_device.set_trb_type(TrbType::AddressDeviceCommand);
+    address_device.set_slot_id(slot_id);
+    let ev = drv.command(&mut m, address_device);
+    assert_eq!(ev.completion_code_raw(), CompletionCode::Success.as_u8());
+    assert_eq!(ev.slot_id(), slot_id);
+
+    // The output Slot Context reports the SuperSpeed port speed.
+    let slot_dw0 = m.read_physical_u32(dev_ctx);
+    assert_eq!((slot_dw0 >> 20) & 0xf, u32::from(regs::PSIV_SUPER_SPEED));
+
+    // GET_DESCRIPTOR(Device) on EP0: Setup (IN data) / Data (IN) / Status (OUT, IOC).
+    let setup_bytes = [0x80u8, 0x06, 0x00, 0x01, 0x00, 0x00, 18, 0x00];
+    let mut setup = Trb::new(u64::from_le_bytes(setup_bytes), 8, 3 << 16);
+    setup.set_trb_type(TrbType::SetupStage);
+    setup.set_cycle(true);
+    // Immediate Data: the setup packet lives in the TRB parameter.
+    setup.control |= 1 << 6;
+    let mut data = Trb::new(data_buf, 18, Trb::CONTROL_DIR);
+    data.set_trb_type(TrbType::DataStage);
+    data.set_cycle(true);
+    let mut status = Trb::new(0, 0, Trb::CONTROL_IOC);
+    status.set_trb_type(TrbType::StatusStage);
+    status.set_cycle(true);
+    for (i, trb) in [setup, data, status].iter().enumerate() {
+        m.write_physical(ep0_ring + (i * TRB_LEN) as u64, &trb.to_bytes());
+    }
+
+    // EP0 doorbell target is endpoint ID 1.
+    drv.ring_doorbell(&mut m, slot_id, 1);
+    let ev = drv.next_event(&mut m);
+    assert_eq!(ev.trb_type(), TrbType::TransferEvent);
+    assert_eq!(ev.completion_code_raw(), CompletionCode::Success.as_u8());
+    assert_eq!(ev.slot_id(), slot_id);
+    assert_eq!(ev.endpoint_id(), 1);
+    assert_eq!(ev.parameter, ep0_ring + 2 * TRB_LEN as u64);
+
+    assert_eq!(
+        m.read_physical_bytes(data_buf, DEVICE_DESCRIPTOR.len()),
+        DEVICE_DESCRIPTOR.to_vec()
+    );
+
+    // Unplug: the guest sees a disconnect and the port is no longer recorded.
+    m.xhci_detach_device(port).unwrap();
+    assert!(m.xhci_attached_ports().is_empty());
+    let ev = drv.next_event(&mut m);
+    assert_eq!(ev.trb_type(), TrbType::PortStatusChangeEvent);
+    let portsc = drv.read_portsc(&mut m, port);
+    assert_eq!(portsc & (regs::PORTSC_CCS | regs::PORTSC_PED), 0);
+    assert_ne!(portsc & regs::PORTSC_CSC, 0);
+}
+
+#[test]
+fn xhci_attach_device_rejects_bad_ports() {
+    let mut m = Machine::new(minimal_xhci_config()).unwrap();
+
+    // Only SuperSpeed devices can train a link on a USB3 port.
+    let slow = aero_usb::hid::UsbHidKeyboardHandle::new();
+    assert_eq!(
+        m.xhci_attach_device(Machine::XHCI_FIRST_USB3_PORT, Box::new(slow.clone())),
+        Err(UsbHubAttachError::InvalidPort)
+    );
+    let past_end = Machine::XHCI_USB2_PORT_COUNT + Machine::XHCI_USB3_PORT_COUNT;
+    assert_eq!(
+        m.xhci_attach_device(past_end, Box::new(SuperSpeedDevice)),
+        Err(UsbHubAttachError::InvalidPort)
+    );
+    assert!(m.xhci_attached_ports().is_empty());
+
+    // USB2 ports take both.
+    m.xhci_attach_device(1, Box::new(slow)).unwrap();
+    m.xhci_attach_device(2, Box::new(SuperSpeedDevice)).unwrap();
+    assert_eq!(m.xhci_attached_ports(), vec![1, 2]);
+
+    // Without xHCI the device is handed back as an error rather than dropped.
+    let mut m = Machine::new(MachineConfig {
+        enable_xhci: false,
+        ..minimal_xhci_config()
+    })
+    .unwrap();
+    assert_eq!(
+        m.xhci_attach_device(0, Box::new(SuperSpeedDevice)),
+        Err(UsbHubAttachError::InvalidPort)
+    );
+}
+
+#[test]
+fn synthetic_usb_hid_can_live_behind_xhci() {
+    let mut m = Machine::new(MachineConfig {
+        enable_synthetic_usb_hid: true,
+        synthetic_usb_hid_on_xhci: true,
+        ..minimal_xhci_config()
+    })
+    .unwrap();
+    assert!(m.uhci().is_none());
+    assert!(m.usb_hid_keyboard_handle().is_some());
+
+    let xhci = m.xhci().expect("xHCI should be enabled");
+    {
+        let mut xhci = xhci.borrow_mut();
+        let hub = xhci
+            .controller_mut()
+            .port_device(usize::from(Machine::XHCI_EXTERNAL_HUB_ROOT_PORT))
+            .expect("external hub should be attached to the xHCI root port");
+        assert_eq!(
+            hub.model().hub_port_count(),
+            Some(Machine::XHCI_EXTERNAL_HUB_PORT_COUNT)
+        );
+    }
+
+    // The hub port is occupied, so host-provided devices must go elsewhere.
+    assert!(m
+        .xhci_attach_device(
+            Machine::XHCI_EXTERNAL_HUB_ROOT_PORT,
+            Box::new(SuperSpeedDevice)
+        )
+        .is_err());
+}
@@ -0,0 +1,190 @@
+use aero_machine::{
+    paravirt_feature_bits, AeroFeatureId, Machine, MachineConfig, RunExit, AERO_CPUID_BASE_LEAF,
+    AERO_INPUT_BATCH_PROTOCOL_VERSION, AERO_MACHINE_ABI_VERSION, AERO_MSR_BASE,
+};
+use pretty_assertions::assert_eq;
+
+const CODE_ADDR: u64 = 0x1000;
+const OUT_ADDR: u16 = 0x0500;
+
+/// Registers stored by [`cpuid_stub`], in order.
+#[derive(Debug, PartialEq, Eq)]
+struct Probe {
+    leaf1_ecx: u32,
+    vendor: [u32; 4],
+    info: [u32; 4],
+    msr_count: u32,
+    msr_first: u32,
+}
+
+fn store(code: &mut Vec<u8>, modrm_reg: u8, slot: u16) {
+    // mov [OUT_ADDR + slot*4], e<reg>
+    code.extend_from_slice(&[0x66, 0x89, 0x06 | (modrm_reg << 3)]);
+    code.extend_from_slice(&(OUT_ADDR + slot * 4).to_le_bytes());
+}
+
+fn cpuid(code: &mut Vec<u8>, leaf: u32) {
+    code.extend_from_slice(&[0x66, 0xB8]); // mov eax, leaf
+    code.extend_from_slice(&leaf.to_le_bytes());
+    code.extend_from_slice(&[0x66, 0x31, 0xC9]); // xor ecx, ecx
+    code.extend_from_slice(&[0x0F, 0xA2]); // cpuid
+}
+
+fn rdmsr(code: &mut Vec<u8>, index: u32) {
+    code.extend_from_slice(&[0x66, 0xB9]); // mov ecx, index
+    code.extend_from_slice(&index.to_le_bytes());
+    code.extend_from_slice(&[0x0F, 0x32]); // rdmsr
+}
+
+/// Real-mode stub querying CPUID.1, the Aero CPUID block and the first two Aero MSRs.
+fn cpuid_stub() -> Vec<u8> {
+    const EAX: u8 = 0;
+    const ECX: u8 = 1;
+    const EDX: u8 = 2;
+    const EBX: u8 = 3;
+
+    let mut code = Vec::new();
+    cpuid(&mut code, 1);
+    store(&mut code, ECX, 0);
+    for (i, leaf) in [AERO_CPUID_BASE_LEAF, AERO_CPUID_BASE_LEAF + 1]
+        .into_iter()
+        .enumerate()
+    {
+        cpuid(&mut code, leaf);
+        let base = 1 + 4 * i as u16;
+        for (slot, reg) in [EAX, EBX, ECX, EDX].into_iter().enumerate() {
+            store(&mut code, reg, base + slot as u16);
+        }
+    }
+    rdmsr(&mut code, AERO_MSR_BASE);
+    store(&mut code, EAX, 9);
+    rdmsr(&mut code, AERO_MSR_BASE + 1);
+    store(&mut code, EAX, 10);
+    code.push(0xF4); // hlt
+    code
+}
+
+fn run_stub(m: &mut Machine) -> Probe {
+    m.write_physical(CODE_ADDR, &cpuid_stub());
+    let cpu = m.cpu_mut();
+    for seg in [
+        &mut cpu.segments.cs,
+        &mut cpu.segments.ds,
+        &mut cpu.segments.es,
+        &mut cpu.segments.ss,
+    ] {
+        seg.selector = 0;
+        seg.base = 0;
+        seg.limit = 0xFFFF;
+        seg.access = 0;
+    }
+    cpu.set_stack_ptr(0x7000);
+    cpu.set_rip(CODE_ADDR);
+    cpu.set_rflags(0x002);
+    cpu.halted = false;
+
+    let exit = m.run_slice(1_000);
+    assert!(matches!(exit, RunExit::Halted { .. }), "{exit:?}");
+
+    let mut reg = |slot: u16| m.read_physical_u32(u64::from(OUT_ADDR + slot * 4));
+    Probe {
+        leaf1_ecx: reg(0),
+        vendor: [reg(1), reg(2), reg(3), reg(4)],
+        info: [reg(5), reg(6), reg(7), reg(8)],
+        msr_count: reg(9),
+        msr_first: reg(10),
+    }
+}
+
+fn minimal_cfg() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    }
+}
+
+fn entry(id: AeroFeatureId, version: u32) -> u32 {
+    (u32::from(id as u16) << 16) | version
+}
+
+#[test]
+fn cpuid_reports_aero_signature_and_config_derived_features() {
+    let mut m = Machine::new(minimal_cfg()).unwrap();
+    let probe = run_stub(&mut m);
+
+    assert_ne!(probe.leaf1_ecx & (1 << 31), 0, "hypervisor-present bit");
+    assert_eq!(
+        probe.vendor,
+        [
+            AERO_CPUID_BASE_LEAF + 1,
+            u32::from_le_bytes(*b"Aero"),
+            u32::from_le_bytes(*b"Aero"),
+            0,
+        ]
+    );
+    assert_eq!(
+        probe.info,
+        [
+            AERO_MACHINE_ABI_VERSION,
+            paravirt_feature_bits::EXTENDED_FEATURE_MSRS,
+            AERO_INPUT_BATCH_PROTOCOL_VERSION,
+            AERO_MSR_BASE,
+        ]
+    );
+    assert_eq!(probe.msr_count, 1);
+    assert_eq!(
+        probe.msr_first,
+        entry(AeroFeatureId::InputBatch, AERO_INPUT_BATCH_PROTOCOL_VERSION)
+    );
+
+    let info = m.paravirt_info();
+    assert_eq!(info.features, probe.info[1]);
+    assert_eq!(info.extended_features, vec![probe.msr_first]);
+}
+
+#[test]
+fn cpuid_tracks_aerogpu_bridge_and_virtio_and_survives_snapshot_restore() {
+    let cfg = MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_aerogpu: true,
+        enable_virtio_blk: true,
+        ..minimal_cfg()
+    };
+    let mut m = Machine::new(cfg.clone()).unwrap();
+    let gpu_and_virtio = paravirt_feature_bits::EXTENDED_FEATURE_MSRS
+        | paravirt_feature_bits::AEROGPU
+        | paravirt_feature_bits::VIRTIO;
+    assert_eq!(run_stub(&mut m).info[1], gpu_and_virtio);
+
+    m.aerogpu_enable_submission_bridge();
+    let probe = run_stub(&mut m);
+    assert_eq!(
+        probe.info[1],
+        gpu_and_virtio | paravirt_feature_bits::AEROGPU_SUBMISSION_BRIDGE
+    );
+    assert_eq!(
+        m.paravirt_info().extended_features,
+        vec![
+            entry(AeroFeatureId::AeroGpu, 1),
+            entry(AeroFeatureId::AeroGpuSubmissionBridge, 1),
+            entry(AeroFeatureId::VirtioBlk, 1),
+            entry(AeroFeatureId::InputBatch, AERO_INPUT_BATCH_PROTOCOL_VERSION),
+        ]
+    );
+    assert_eq!(probe.msr_count, 4);
+    assert_eq!(probe.msr_first, entry(AeroFeatureId::AeroGpu, 1));
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = Machine::new(cfg).unwrap();
+    restored.restore_snapshot_bytes(&snap).unwrap();
+    assert_eq!(run_stub(&mut restored), probe);
+    assert_eq!(restored.paravirt_info(), m.paravirt_info());
+}
@@ -0,0 +1,69 @@
+use aero_devices::clock::Clock as _;
+use aero_devices::pit8254::{PIT_CH2, PIT_CMD, PIT_PORT_B};
+use aero_machine::{Machine, MachineConfig, SpeakerEvent};
+use pretty_assertions::assert_eq;
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn one_khz_beep_produces_single_speaker_event() {
+    let mut m = new_machine();
+    m.tick_platform(5_000_000);
+    let start_ns = m.platform_clock().unwrap().now_ns();
+
+    // Channel 2, lobyte/hibyte, mode 3 (square wave), divisor 1193 (~1kHz).
+    m.io_write(PIT_CMD, 1, 0xB6);
+    m.io_write(PIT_CH2, 1, 1193 & 0xFF);
+    m.io_write(PIT_CH2, 1, 1193 >> 8);
+    assert!(m.take_speaker_events().is_empty());
+
+    // Gate channel 2 and enable the speaker for 100ms of guest time.
+    let port_b = m.io_read(PIT_PORT_B, 1);
+    m.io_write(PIT_PORT_B, 1, port_b | 0x03);
+    m.tick_platform(100_000_000);
+    assert!(m.take_speaker_events().is_empty());
+    m.io_write(PIT_PORT_B, 1, port_b & !0x03);
+
+    assert_eq!(
+        m.take_speaker_events(),
+        vec![SpeakerEvent {
+            start_ns,
+            duration_ns: 100_000_000,
+            frequency_hz: 1000,
+        }]
+    );
+    assert!(m.take_speaker_events().is_empty());
+}
+
+#[test]
+fn port_b_timer2_output_toggles_with_square_wave() {
+    let mut m = new_machine();
+
+    // Channel 2, mode 3, divisor 1193: OUT2 is high for the first ~0.5ms of each period.
+    m.io_write(PIT_CMD, 1, 0xB6);
+    m.io_write(PIT_CH2, 1, 1193 & 0xFF);
+    m.io_write(PIT_CH2, 1, 1193 >> 8);
+    m.io_write(PIT_PORT_B, 1, 0x01);
+
+    let out2 = |m: &mut Machine| m.io_read(PIT_PORT_B, 1) & 0x20 != 0;
+    m.tick_platform(250_000);
+    assert!(out2(&mut m));
+    m.tick_platform(500_000);
+    assert!(!out2(&mut m));
+    m.tick_platform(500_000);
+    assert!(out2(&mut m));
+
+    // Gate-only (speaker data disabled) is silent.
+    assert!(m.take_speaker_events().is_empty());
+}
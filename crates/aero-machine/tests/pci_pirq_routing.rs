@@ -0,0 +1,127 @@
+use aero_devices::pci::profile::{ISA_PIIX3, SATA_AHCI_ICH9};
+use aero_devices::pci::{PciBdf, PciInterruptPin, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig};
+use aero_storage::{MemBackend, RawDisk, SECTOR_SIZE};
+use pretty_assertions::assert_eq;
+
+const GHC: u64 = 0x04;
+const GHC_IE: u64 = 1 << 1;
+const GHC_AE: u64 = 1 << 31;
+const PORT0_IE: u64 = 0x100 + 0x14;
+const PORT_IS_PCS: u64 = 1 << 6;
+
+fn cfg_addr(bdf: PciBdf, offset: u8) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device) << 11)
+        | (u32::from(bdf.function) << 8)
+        | u32::from(offset & 0xFC)
+}
+
+fn read_cfg_u8(m: &mut Machine, bdf: PciBdf, offset: u8) -> u8 {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_read(PCI_CFG_DATA_PORT + u16::from(offset & 3), 1) as u8
+}
+
+fn write_cfg_u8(m: &mut Machine, bdf: PciBdf, offset: u8, value: u8) {
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(bdf, offset));
+    m.io_write(
+        PCI_CFG_DATA_PORT + u16::from(offset & 3),
+        1,
+        u32::from(value),
+    );
+}
+
+fn gsi_level(m: &Machine, gsi: u32) -> bool {
+    m.platform_interrupts().unwrap().borrow().gsi_level(gsi)
+}
+
+/// Builds a machine with AHCI and the PIIX3 ISA bridge, with the AHCI port 0 change interrupt
+/// asserted.
+///
+/// AHCI sits at 00:02.0, so its INTA# swizzles onto PIRQC (IRQ12 by default).
+fn machine_with_asserted_ahci_intx() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_ahci: true,
+        enable_ide: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let disk = RawDisk::create(MemBackend::new(), 8 * SECTOR_SIZE as u64).unwrap();
+    m.attach_ahci_disk_port0(Box::new(disk)).unwrap();
+
+    // Memory space enable, then let the machine sync the command register into the device model.
+    m.io_write(PCI_CFG_ADDR_PORT, 4, cfg_addr(SATA_AHCI_ICH9.bdf, 0x04));
+    m.io_write(PCI_CFG_DATA_PORT, 2, 0x0006);
+    m.poll_pci_intx_lines();
+
+    let ahci = m.ahci().unwrap();
+    {
+        let mut ahci = ahci.borrow_mut();
+        ahci.mmio_write(GHC, 4, GHC_AE | GHC_IE);
+        ahci.mmio_write(PORT0_IE, 4, PORT_IS_PCS);
+        ahci.notify_device_changed(0);
+    }
+    m.poll_pci_intx_lines();
+    m
+}
+
+const AHCI_PIRQ: u8 = 2;
+const PIRQRC_AHCI: u8 = 0x60 + AHCI_PIRQ;
+
+#[test]
+fn pirq_route_registers_power_on_with_default_routing() {
+    let mut m = machine_with_asserted_ahci_intx();
+    let bdf = ISA_PIIX3.bdf;
+    let routes: Vec<u8> = (0..4).map(|i| read_cfg_u8(&mut m, bdf, 0x60 + i)).collect();
+    assert_eq!(routes, vec![10, 11, 12, 13]);
+
+    let router = m.pci_intx_router().unwrap();
+    assert_eq!(
+        router
+            .borrow()
+            .pirq_index(SATA_AHCI_ICH9.bdf, PciInterruptPin::IntA),
+        usize::from(AHCI_PIRQ)
+    );
+    assert!(gsi_level(&m, 12));
+}
+
+#[test]
+fn moving_ahci_pirq_reroutes_its_interrupt() {
+    let mut m = machine_with_asserted_ahci_intx();
+    assert!(gsi_level(&m, 12));
+    assert!(!gsi_level(&m, 11));
+
+    write_cfg_u8(&mut m, ISA_PIIX3.bdf, PIRQRC_AHCI, 11);
+    assert_eq!(read_cfg_u8(&mut m, ISA_PIIX3.bdf, PIRQRC_AHCI), 11);
+    m.poll_pci_intx_lines();
+
+    assert!(!gsi_level(&m, 12));
+    assert!(gsi_level(&m, 11));
+    assert_eq!(
+        m.pci_intx_router()
+            .unwrap()
+            .borrow()
+            .pirq_route(usize::from(AHCI_PIRQ)),
+        Some(11)
+    );
+
+    // Bit 7 disconnects the PIRQ from the ISA IRQs entirely.
+    write_cfg_u8(&mut m, ISA_PIIX3.bdf, PIRQRC_AHCI, 0x8B);
+    m.poll_pci_intx_lines();
+    assert!(!gsi_level(&m, 11));
+    assert!(!gsi_level(&m, 12));
+    assert_eq!(
+        m.pci_intx_router()
+            .unwrap()
+            .borrow()
+            .pirq_route(usize::from(AHCI_PIRQ)),
+        None
+    );
+}
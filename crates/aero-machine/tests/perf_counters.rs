@@ -0,0 +1,101 @@
+use aero_cpu_core::AssistReason;
+use aero_machine::{Machine, MachineConfig, MachinePerfCounters, RunExit};
+use pretty_assertions::assert_eq;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Runs until HLT, returning `(run_slice calls, instructions executed)`.
+fn run_until_halt(m: &mut Machine) -> (u64, u64) {
+    let mut total = 0u64;
+    for calls in 1..=100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { executed } => return (calls, total + executed),
+            RunExit::Completed { executed } => total += executed,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+#[test]
+fn perf_counters_track_guest_activity_and_reset() {
+    let mut m = new_machine();
+    // mov dx, 0x80; out dx, al; in al, dx
+    // mov ax, 0xB800; mov es, ax; mov byte [es:0], 'A'
+    // xor ax, ax; cpuid; cli; hlt
+    let code = [
+        0xBA, 0x80, 0x00, 0xEE, 0xEC, 0xB8, 0x00, 0xB8, 0x8E, 0xC0, 0x26, 0xC6, 0x06, 0x00, 0x00,
+        0x41, 0x31, 0xC0, 0x0F, 0xA2, 0xFA, 0xF4,
+    ];
+    m.set_disk_image(boot_sector(&code)).unwrap();
+    m.reset();
+    m.reset_perf_counters();
+
+    let (calls, executed) = run_until_halt(&mut m);
+    let perf = m.perf_counters();
+    assert_eq!(perf.run_slice_calls, calls);
+    assert_eq!(perf.instructions, vec![executed]);
+    assert_eq!(perf.total_instructions(), executed);
+    assert!(perf.port_io.reads >= 1, "{perf:?}");
+    assert!(perf.port_io.writes >= 1, "{perf:?}");
+    assert!(perf.mmio.vga_legacy.writes >= 1, "{perf:?}");
+    assert!(perf.assist_exits_for(AssistReason::Cpuid) >= 1, "{perf:?}");
+    assert!(perf.device_ticks.pit >= 1, "{perf:?}");
+    assert_eq!(perf.exceptions, [0; 32]);
+
+    // Counters are host diagnostics: snapshot restore and machine reset do not clear them.
+    let snapshot = m.take_snapshot_full().unwrap();
+    m.restore_snapshot_bytes(&snapshot).unwrap();
+    assert_eq!(m.perf_counters(), perf);
+    m.reset();
+    let after_reset = m.perf_counters();
+    assert_eq!(after_reset.instructions, perf.instructions);
+    assert_eq!(after_reset.run_slice_calls, perf.run_slice_calls);
+    assert_eq!(after_reset.assist_exits, perf.assist_exits);
+
+    m.reset_perf_counters();
+    assert_eq!(
+        m.perf_counters(),
+        MachinePerfCounters {
+            instructions: vec![0],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn perf_counters_count_exceptions_by_vector() {
+    let mut m = new_machine();
+    // ud2
+    m.set_disk_image(boot_sector(&[0x0F, 0x0B])).unwrap();
+    m.reset();
+
+    match m.run_slice(10_000) {
+        RunExit::Exception { .. } => {}
+        other => panic!("unexpected exit: {other:?}"),
+    }
+    let perf = m.perf_counters();
+    assert_eq!(perf.exceptions[6], 1);
+    assert_eq!(perf.exceptions.iter().sum::<u64>(), 1);
+    assert_eq!(perf.memory_faults, 0);
+}
@@ -0,0 +1,355 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::i8042::{I8042_DATA_PORT, I8042_STATUS_PORT};
+use aero_devices::pci::{profile, PciBdf};
+use aero_machine::{
+    Machine, MachineConfig, PointerCoalescing, PointerEventCounters, PointerQueueFullPolicy,
+};
+use aero_virtio::devices::input::{
+    VirtioInput, BTN_LEFT, EV_KEY, EV_REL, EV_SYN, REL_WHEEL, REL_X, REL_Y, SYN_REPORT,
+};
+use aero_virtio::pci::{
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use aero_virtio::queue::VIRTQ_DESC_F_WRITE;
+use pretty_assertions::assert_eq;
+
+const EVENTQ_DESC: u64 = 0x10000;
+const EVENTQ_AVAIL: u64 = 0x11000;
+const EVENTQ_USED: u64 = 0x12000;
+const EVENT_BUFS: u64 = 0x13000;
+const EVENT_BUF_COUNT: u16 = 32;
+
+fn virtio_mouse_machine(coalescing: PointerCoalescing, policy: PointerQueueFullPolicy) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_virtio_input: true,
+        pointer_coalescing: coalescing,
+        pointer_queue_full_policy: policy,
+        // Keep deterministic and focused.
+        enable_serial: false,
+        enable_i8042: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn cfg_addr(bdf: PciBdf, offset: u16) -> u32 {
+    0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device & 0x1F) << 11)
+        | (u32::from(bdf.function & 0x07) << 8)
+        | (u32::from(offset) & 0xFC)
+}
+
+fn cfg_read(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8) -> u32 {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_read(0xCFC + (offset & 3), size)
+}
+
+fn cfg_write(m: &mut Machine, bdf: PciBdf, offset: u16, size: u8, value: u32) {
+    m.io_write(0xCF8, 4, cfg_addr(bdf, offset));
+    m.io_write(0xCFC + (offset & 3), size, value);
+}
+
+/// Bring up the virtio-input mouse like a guest driver and post `EVENT_BUF_COUNT` 8-byte event
+/// buffers on the event queue.
+fn init_virtio_mouse_driver(m: &mut Machine) {
+    let bdf = profile::VIRTIO_INPUT_MOUSE.bdf;
+    let bar0 = m
+        .pci_bar_base(bdf, profile::VIRTIO_BAR0_INDEX)
+        .expect("virtio-input BAR0 must be assigned by BIOS POST");
+    let cmd = cfg_read(m, bdf, 0x04, 2);
+    cfg_write(m, bdf, 0x04, 2, cmd | 0x0006); // MEM + BUSMASTER
+
+    let common = bar0;
+    let notify = bar0 + 0x1000;
+
+    m.write_physical_u8(common + 0x14, VIRTIO_STATUS_ACKNOWLEDGE);
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER,
+    );
+    for select in 0..2u32 {
+        m.write_physical_u32(common, select);
+        let features = m.read_physical_u32(common + 0x04);
+        m.write_physical_u32(common + 0x08, select);
+        m.write_physical_u32(common + 0x0c, features);
+    }
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_FEATURES_OK,
+    );
+    m.write_physical_u8(
+        common + 0x14,
+        VIRTIO_STATUS_ACKNOWLEDGE
+            | VIRTIO_STATUS_DRIVER
+            | VIRTIO_STATUS_FEATURES_OK
+            | VIRTIO_STATUS_DRIVER_OK,
+    );
+
+    m.write_physical_u16(common + 0x16, 0); // queue_select
+    m.write_physical_u64(common + 0x20, EVENTQ_DESC);
+    m.write_physical_u64(common + 0x28, EVENTQ_AVAIL);
+    m.write_physical_u64(common + 0x30, EVENTQ_USED);
+    m.write_physical_u16(common + 0x1c, 1); // queue_enable
+
+    for i in 0..EVENT_BUF_COUNT {
+        let desc = EVENTQ_DESC + u64::from(i) * 16;
+        m.write_physical_u64(desc, EVENT_BUFS + u64::from(i) * 8);
+        m.write_physical_u32(desc + 8, 8);
+        m.write_physical_u16(desc + 12, VIRTQ_DESC_F_WRITE);
+        m.write_physical_u16(desc + 14, 0);
+        m.write_physical_u16(EVENTQ_AVAIL + 4 + u64::from(i) * 2, i);
+    }
+    m.write_physical_u16(EVENTQ_AVAIL, 0);
+    m.write_physical_u16(EVENTQ_AVAIL + 2, EVENT_BUF_COUNT);
+    m.write_physical_u16(EVENTQ_USED, 0);
+    m.write_physical_u16(EVENTQ_USED + 2, 0);
+
+    m.write_physical_u16(notify, 0);
+}
+
+/// Events the guest has received so far, in delivery order.
+fn guest_events(m: &mut Machine) -> Vec<(u16, u16, i32)> {
+    let used = m.read_physical_u16(EVENTQ_USED + 2);
+    (0..used)
+        .map(|i| {
+            let id = m.read_physical_u32(EVENTQ_USED + 4 + u64::from(i) * 8);
+            let ev = m.read_physical_bytes(EVENT_BUFS + u64::from(id) * 8, 8);
+            (
+                u16::from_le_bytes([ev[0], ev[1]]),
+                u16::from_le_bytes([ev[2], ev[3]]),
+                i32::from_le_bytes([ev[4], ev[5], ev[6], ev[7]]),
+            )
+        })
+        .collect()
+}
+
+fn virtio_mouse_pending(m: &Machine) -> usize {
+    m.virtio_input_mouse()
+        .unwrap()
+        .borrow()
+        .device::<VirtioInput>()
+        .unwrap()
+        .pending_events_len()
+}
+
+#[test]
+fn virtio_mouse_motion_within_one_slice_becomes_one_frame() {
+    let mut m = virtio_mouse_machine(
+        PointerCoalescing::PerSlice,
+        PointerQueueFullPolicy::DropOldestMotion,
+    );
+    init_virtio_mouse_driver(&mut m);
+
+    for i in 0..1000 {
+        m.inject_virtio_rel(1, if i % 2 == 0 { -1 } else { 0 });
+    }
+    m.inject_virtio_wheel(1);
+    m.inject_virtio_wheel(2);
+    assert!(
+        guest_events(&mut m).is_empty(),
+        "motion waits for the slice"
+    );
+
+    m.run_slice(1);
+    assert_eq!(
+        guest_events(&mut m),
+        vec![
+            (EV_REL, REL_X, 1000),
+            (EV_REL, REL_Y, -500),
+            (EV_REL, REL_WHEEL, 3),
+            (EV_SYN, SYN_REPORT, 0),
+        ]
+    );
+    assert_eq!(
+        m.virtio_mouse_input_counters(),
+        PointerEventCounters {
+            injected: 1002,
+            coalesced: 1001,
+            dropped: 0,
+        }
+    );
+}
+
+#[test]
+fn virtio_mouse_buttons_split_coalesced_motion() {
+    let mut m = virtio_mouse_machine(
+        PointerCoalescing::Window {
+            window_ns: 1_000_000_000,
+        },
+        PointerQueueFullPolicy::DropOldestMotion,
+    );
+    init_virtio_mouse_driver(&mut m);
+
+    m.inject_virtio_rel(1, 0);
+    m.inject_virtio_rel(1, 0);
+    m.inject_virtio_button(BTN_LEFT, true);
+    m.inject_virtio_rel(2, 0);
+    m.inject_virtio_rel(-1, 0);
+    m.inject_virtio_button(BTN_LEFT, false);
+    m.inject_virtio_rel(5, 0);
+
+    // Buttons are delivered immediately (after the motion queued before them); the trailing motion
+    // waits for its window.
+    m.run_slice(1);
+    let frames = vec![
+        (EV_REL, REL_X, 2),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_KEY, BTN_LEFT, 1),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_REL, REL_X, 1),
+        (EV_SYN, SYN_REPORT, 0),
+        (EV_KEY, BTN_LEFT, 0),
+        (EV_SYN, SYN_REPORT, 0),
+    ];
+    assert_eq!(guest_events(&mut m), frames);
+
+    m.flush_pointer_input();
+    let mut expected = frames;
+    expected.extend([(EV_REL, REL_X, 5), (EV_SYN, SYN_REPORT, 0)]);
+    assert_eq!(guest_events(&mut m), expected);
+    assert_eq!(m.virtio_mouse_input_counters().coalesced, 2);
+}
+
+#[test]
+fn virtio_mouse_full_queue_drops_oldest_motion_but_keeps_buttons() {
+    // No driver: everything stays queued in the device (capacity 4096 events).
+    let mut m = virtio_mouse_machine(
+        PointerCoalescing::Off,
+        PointerQueueFullPolicy::DropOldestMotion,
+    );
+    m.inject_virtio_button(BTN_LEFT, true);
+    for _ in 0..3000 {
+        m.inject_virtio_rel(1, 0);
+    }
+    assert_eq!(virtio_mouse_pending(&m), 4096);
+    // 2 events for the press, 2 per motion frame.
+    assert_eq!(m.virtio_mouse_input_counters().dropped, 3000 - 2047);
+
+    // The press survived eviction: releasing still fits after dropping one more motion frame.
+    m.inject_virtio_button(BTN_LEFT, false);
+    assert_eq!(virtio_mouse_pending(&m), 4096);
+    assert_eq!(m.virtio_mouse_input_counters().dropped, 3000 - 2047 + 1);
+}
+
+#[test]
+fn virtio_mouse_full_queue_blocks_without_dropping() {
+    let mut m = virtio_mouse_machine(PointerCoalescing::PerSlice, PointerQueueFullPolicy::Block);
+    for _ in 0..2048 {
+        m.inject_virtio_button(BTN_LEFT, true);
+    }
+    assert_eq!(virtio_mouse_pending(&m), 4096);
+
+    // Further input waits on the host side; motion keeps merging while blocked.
+    m.inject_virtio_button(BTN_LEFT, false);
+    for _ in 0..1000 {
+        m.inject_virtio_rel(1, 1);
+    }
+    m.run_slice(1);
+    assert_eq!(virtio_mouse_pending(&m), 4096);
+    assert_eq!(
+        m.virtio_mouse_input_counters(),
+        PointerEventCounters {
+            injected: 3049,
+            coalesced: 999,
+            dropped: 0,
+        }
+    );
+}
+
+fn ps2_mouse_machine(coalescing: PointerCoalescing) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_i8042: true,
+        pointer_coalescing: coalescing,
+        enable_serial: false,
+        enable_vga: false,
+        enable_reset_ctrl: false,
+        enable_debugcon: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn drain_i8042_output(m: &mut Machine) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Bound the drain to avoid infinite loops if a buggy device leaves the status bit stuck.
+    for _ in 0..8192 {
+        let status = m.io_read(I8042_STATUS_PORT, 1) as u8;
+        if (status & 0x01) == 0 {
+            break;
+        }
+        out.push(m.io_read(I8042_DATA_PORT, 1) as u8);
+    }
+    out
+}
+
+fn enable_ps2_mouse_reporting(m: &mut Machine) {
+    // 0xD4 routes the next data byte to the mouse; 0xF4 enables data reporting.
+    m.io_write(I8042_STATUS_PORT, 1, 0xD4);
+    m.io_write(I8042_DATA_PORT, 1, 0xF4);
+    let out = drain_i8042_output(m);
+    assert!(out.contains(&0xFA), "expected mouse ACK (got {out:02x?})");
+}
+
+/// Decode standard 3-byte PS/2 packets into `(dx, dy)` (+Y up).
+fn decode_ps2_packets(bytes: &[u8]) -> Vec<(i32, i32)> {
+    assert_eq!(bytes.len() % 3, 0, "partial packet: {bytes:02x?}");
+    bytes
+        .chunks(3)
+        .map(|p| {
+            let dx = i32::from(p[1]) - if p[0] & 0x10 != 0 { 256 } else { 0 };
+            let dy = i32::from(p[2]) - if p[0] & 0x20 != 0 { 256 } else { 0 };
+            (dx, dy)
+        })
+        .collect()
+}
+
+#[test]
+fn ps2_mouse_motion_within_one_slice_is_summed() {
+    let mut m = ps2_mouse_machine(PointerCoalescing::PerSlice);
+    enable_ps2_mouse_reporting(&mut m);
+
+    for _ in 0..1000 {
+        m.inject_mouse_motion(1, 1, 0);
+    }
+    assert!(drain_i8042_output(&mut m).is_empty());
+
+    m.flush_pointer_input();
+    let packets = decode_ps2_packets(&drain_i8042_output(&mut m));
+    // 1000 counts split into packets of at most 127 per axis.
+    assert_eq!(packets.len(), 8);
+    let (sum_x, sum_y) = packets
+        .iter()
+        .fold((0, 0), |(x, y), &(dx, dy)| (x + dx, y + dy));
+    assert_eq!((sum_x, sum_y), (1000, -1000));
+    assert_eq!(
+        m.ps2_mouse_input_counters(),
+        PointerEventCounters {
+            injected: 1000,
+            coalesced: 999,
+            dropped: 0,
+        }
+    );
+}
+
+#[test]
+fn ps2_mouse_without_coalescing_emits_a_packet_per_injection() {
+    let mut m = ps2_mouse_machine(PointerCoalescing::Off);
+    enable_ps2_mouse_reporting(&mut m);
+
+    for _ in 0..1000 {
+        m.inject_mouse_motion(1, 1, 0);
+    }
+    let packets = decode_ps2_packets(&drain_i8042_output(&mut m));
+    assert_eq!(packets.len(), 1000);
+    assert!(packets.iter().all(|&p| p == (1, -1)));
+    assert_eq!(m.ps2_mouse_input_counters().coalesced, 0);
+}
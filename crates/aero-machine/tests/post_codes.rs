@@ -0,0 +1,99 @@
+use aero_machine::{BiosPostPhase, Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+/// Posts `0x42` on port 0x80 (like an OS loader reporting progress), then halts.
+const GUEST_POST_CODE: [u8; 8] = [
+    0xFA, // cli
+    0xB0, 0x42, // mov al, 0x42
+    0xE6, 0x80, // out 0x80, al
+    0xF4, // hlt
+    0xEB, 0xFD, // jmp hlt
+];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(boot: Vec<u8>) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot).unwrap();
+    m.reset();
+    m
+}
+
+fn phases(m: &Machine) -> Vec<BiosPostPhase> {
+    m.post_code_history()
+        .iter()
+        .map(|record| BiosPostPhase::from_code(record.code).unwrap())
+        .collect()
+}
+
+#[test]
+fn bios_posts_phase_codes_through_boot_handoff() {
+    let m = new_machine(boot_sector(&GUEST_POST_CODE));
+
+    assert_eq!(
+        phases(&m),
+        vec![
+            BiosPostPhase::CpuInit,
+            BiosPostPhase::MemoryInit,
+            BiosPostPhase::VideoInit,
+            BiosPostPhase::InterruptVectors,
+            BiosPostPhase::Smbios,
+            BiosPostPhase::PciEnumeration,
+            BiosPostPhase::AcpiTables,
+            BiosPostPhase::BootDeviceSelection,
+            BiosPostPhase::BootHandoff,
+        ]
+    );
+    assert_eq!(m.last_post_code(), Some(BiosPostPhase::BootHandoff.code()));
+}
+
+#[test]
+fn guest_port_0x80_writes_append_to_the_history() {
+    let mut m = new_machine(boot_sector(&GUEST_POST_CODE));
+    let post_len = m.post_code_history().len();
+
+    assert!(matches!(m.run_slice(10_000), RunExit::Halted { .. }));
+
+    let history = m.post_code_history();
+    assert_eq!(history.len(), post_len + 1);
+    let guest = history[post_len];
+    assert_eq!(guest.code, 0x42);
+    assert!(guest.guest_time_ns >= history[post_len - 1].guest_time_ns);
+    assert_eq!(m.last_post_code(), Some(0x42));
+    // Port 0x80 is still the DMA page register underneath and reads back the write.
+    assert_eq!(m.io_read(0x80, 1), 0x42);
+
+    // Reset starts a fresh history from the new POST.
+    m.reset();
+    assert_eq!(m.post_code_history().len(), post_len);
+    assert_eq!(m.last_post_code(), Some(BiosPostPhase::BootHandoff.code()));
+}
+
+#[test]
+fn failed_boot_ends_with_the_failure_code() {
+    let m = new_machine(vec![0u8; aero_storage::SECTOR_SIZE]);
+
+    let phases = phases(&m);
+    assert_eq!(
+        phases[phases.len() - 2..],
+        [
+            BiosPostPhase::BootDeviceSelection,
+            BiosPostPhase::BootFailure
+        ]
+    );
+}
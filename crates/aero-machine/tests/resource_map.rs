@@ -0,0 +1,82 @@
+use aero_devices::pci::profile::{ISA_PIIX3, SATA_AHCI_ICH9};
+use aero_devices::pci::{PciBdf, PCI_CFG_ADDR_PORT, PCI_CFG_DATA_PORT};
+use aero_machine::{Machine, MachineConfig, Resource, ResourceClaim, ResourceMismatch};
+use pretty_assertions::assert_eq;
+
+fn browser_machine() -> Machine {
+    Machine::new(MachineConfig::browser_defaults(64 * 1024 * 1024)).unwrap()
+}
+
+fn has_claim(claims: &[ResourceClaim], owner: &str, resource: Resource) -> bool {
+    claims
+        .iter()
+        .any(|c| c.owner == owner && c.resource == resource)
+}
+
+#[test]
+fn browser_defaults_resources_match_acpi_tables() {
+    let mut m = browser_machine();
+    let map = m.debug_resource_map();
+
+    assert!(map.acpi.is_some(), "browser defaults publish ACPI tables");
+    assert_eq!(map.mismatches, vec![]);
+
+    assert!(has_claim(
+        &map.claims,
+        "pit8254",
+        Resource::Io {
+            start: 0x40,
+            end: 0x43
+        }
+    ));
+    assert!(has_claim(&map.claims, "pit8254", Resource::Irq(0)));
+    assert!(has_claim(&map.claims, "acpi-pm", Resource::Irq(9)));
+    assert!(has_claim(&map.claims, "fdc", Resource::Dma(2)));
+    assert!(has_claim(
+        &map.claims,
+        "hpet",
+        Resource::Mmio {
+            start: 0xFED0_0000,
+            end: 0xFED0_03FF
+        }
+    ));
+    assert!(map
+        .claims
+        .iter()
+        .any(|c| c.owner == "pci-bar" && c.pci == Some(SATA_AHCI_ICH9.bdf)));
+    assert!(map
+        .pci_intx
+        .iter()
+        .any(|r| r.bdf == SATA_AHCI_ICH9.bdf && r.pin == 0 && r.gsi == Some(12)));
+}
+
+#[test]
+fn rerouted_pirq_is_reported_against_prt() {
+    let mut m = browser_machine();
+
+    // Move PIRQC (AHCI's INTA#) from IRQ12 to IRQ11 behind the ACPI tables' back.
+    let bdf = ISA_PIIX3.bdf;
+    let addr = 0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device) << 11)
+        | (u32::from(bdf.function) << 8)
+        | 0x60;
+    m.io_write(PCI_CFG_ADDR_PORT, 4, addr);
+    m.io_write(PCI_CFG_DATA_PORT + 2, 1, 11);
+    m.poll_pci_intx_lines();
+
+    let map = m.debug_resource_map();
+    assert!(map.mismatches.contains(&ResourceMismatch::PrtRoute {
+        bdf: PciBdf::new(0, 2, 0),
+        pin: 0,
+        acpi: Some(12),
+        router: Some(11),
+    }));
+    assert!(map.mismatches.iter().all(|m| matches!(
+        m,
+        ResourceMismatch::PrtRoute {
+            router: Some(11),
+            ..
+        }
+    )));
+}
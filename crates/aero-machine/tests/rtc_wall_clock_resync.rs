@@ -0,0 +1,85 @@
+use aero_devices::acpi_pm::{DEFAULT_PM_TMR_BLK, PM1_STS_RTC};
+use aero_machine::{Machine, MachineConfig};
+use pretty_assertions::assert_eq;
+
+/// 2024-03-15 13:45:30.750 UTC.
+const WALL_CLOCK_UNIX_MS: i64 = 1_710_510_330_750;
+
+fn pc_machine_config() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn cmos_read(m: &mut Machine, reg: u8) -> u8 {
+    m.io_write(0x70, 1, u32::from(reg));
+    m.io_read(0x71, 1) as u8
+}
+
+fn cmos_write(m: &mut Machine, reg: u8, value: u8) {
+    m.io_write(0x70, 1, u32::from(reg));
+    m.io_write(0x71, 1, u32::from(value));
+}
+
+#[test]
+fn resync_after_restore_updates_rtc_but_not_pm_timer() {
+    let mut src = Machine::new(pc_machine_config()).unwrap();
+    src.tick_platform(1_000_000_000);
+    let snap = src.take_snapshot_full().unwrap();
+
+    let mut m = Machine::new(pc_machine_config()).unwrap();
+    m.restore_snapshot_bytes(&snap).unwrap();
+    let pm_tmr = m.io_read(DEFAULT_PM_TMR_BLK, 4);
+    assert_eq!(pm_tmr, 3_579_545);
+
+    m.resync_wall_clock(WALL_CLOCK_UNIX_MS);
+    assert_eq!(m.io_read(DEFAULT_PM_TMR_BLK, 4), pm_tmr);
+
+    // Default register B: 24-hour BCD.
+    assert_eq!(cmos_read(&mut m, 0x00), 0x30);
+    assert_eq!(cmos_read(&mut m, 0x02), 0x45);
+    assert_eq!(cmos_read(&mut m, 0x04), 0x13);
+    assert_eq!(cmos_read(&mut m, 0x07), 0x15);
+    assert_eq!(cmos_read(&mut m, 0x08), 0x03);
+    assert_eq!(cmos_read(&mut m, 0x09), 0x24);
+    assert_eq!(cmos_read(&mut m, 0x32), 0x20);
+
+    // Binary mode reads the same time.
+    let reg_b = cmos_read(&mut m, 0x0B);
+    cmos_write(&mut m, 0x0B, reg_b | 0x04);
+    assert_eq!(cmos_read(&mut m, 0x02), 45);
+    assert_eq!(cmos_read(&mut m, 0x04), 13);
+
+    // Time keeps running from the new wall-clock time on the guest timebase.
+    m.tick_platform(250_000_000);
+    assert_eq!(cmos_read(&mut m, 0x00), 31);
+    assert_eq!(m.io_read(DEFAULT_PM_TMR_BLK, 4), pm_tmr + 894_886);
+}
+
+#[test]
+fn resync_raises_acpi_rtc_event_when_configured() {
+    let mut m = Machine::new(MachineConfig {
+        enable_acpi: true,
+        acpi_rtc_resync_event: true,
+        ..pc_machine_config()
+    })
+    .unwrap();
+    m.resync_wall_clock(WALL_CLOCK_UNIX_MS);
+    let pm = m.acpi_pm().unwrap();
+    assert_ne!(pm.borrow().pm1_status() & PM1_STS_RTC, 0);
+
+    let mut m = Machine::new(MachineConfig {
+        enable_acpi: true,
+        ..pc_machine_config()
+    })
+    .unwrap();
+    m.resync_wall_clock(WALL_CLOCK_UNIX_MS);
+    let pm = m.acpi_pm().unwrap();
+    assert_eq!(pm.borrow().pm1_status() & PM1_STS_RTC, 0);
+}
@@ -0,0 +1,126 @@
+use aero_devices::clock::Clock as _;
+use aero_machine::{
+    Machine, MachineConfig, RunExit, DEFAULT_GUEST_CPU_HZ, RUN_BUDGET_MAX_BATCH_INSTS,
+    RUN_BUDGET_MIN_BATCH_INSTS,
+};
+use pretty_assertions::assert_eq;
+
+// cli; jmp $
+const COMPUTE_LOOP: &[u8] = &[0xFA, 0xEB, 0xFE];
+// cli; in al, 0x61; out 0x80, al; jmp short -6 (back to `in`)
+const IO_LOOP: &[u8] = &[0xFA, 0xE4, 0x61, 0xE6, 0x80, 0xEB, 0xFA];
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+fn guest_now_ns(m: &Machine) -> u64 {
+    m.platform_clock().unwrap().now_ns()
+}
+
+/// Guest time taken by a batch of `insts` instructions (one TSC cycle each), rounded up.
+fn batch_ns(insts: u64) -> u64 {
+    (u128::from(insts) * 1_000_000_000).div_ceil(u128::from(DEFAULT_GUEST_CPU_HZ)) as u64
+}
+
+fn assert_run_for_overshoot_is_bounded(code: &[u8]) {
+    let mut m = new_machine(code);
+    for budget_ns in [1, 50, 10_000, 2_000_000] {
+        let start = guest_now_ns(&m);
+        let exit = m.run_for(budget_ns);
+        let elapsed = guest_now_ns(&m) - start;
+
+        assert!(
+            matches!(exit, RunExit::Completed { executed } if executed > 0),
+            "{exit:?}"
+        );
+        assert!(
+            elapsed >= budget_ns,
+            "budget {budget_ns}ns, elapsed {elapsed}ns"
+        );
+        // Overshoot is at most one internal batch; batches are capped to the remaining budget, so
+        // in practice it stays below even the smallest adaptive batch.
+        let overshoot = elapsed - budget_ns;
+        assert!(
+            overshoot <= batch_ns(RUN_BUDGET_MAX_BATCH_INSTS),
+            "budget {budget_ns}ns overshot by {overshoot}ns"
+        );
+        assert!(
+            overshoot <= batch_ns(RUN_BUDGET_MIN_BATCH_INSTS),
+            "budget {budget_ns}ns overshot by {overshoot}ns"
+        );
+    }
+}
+
+#[test]
+fn run_for_overshoot_is_bounded_for_compute_heavy_code() {
+    assert_run_for_overshoot_is_bounded(COMPUTE_LOOP);
+}
+
+#[test]
+fn run_for_overshoot_is_bounded_for_io_heavy_code() {
+    assert_run_for_overshoot_is_bounded(IO_LOOP);
+}
+
+#[test]
+fn run_for_zero_budget_executes_nothing() {
+    let mut m = new_machine(COMPUTE_LOOP);
+    assert_eq!(m.run_for(0), RunExit::Completed { executed: 0 });
+}
+
+#[test]
+fn run_for_returns_other_exits_with_the_total_executed_count() {
+    // cli; mov cx, 5000; loop $; hlt
+    let mut m = new_machine(&[0xFA, 0xB9, 0x88, 0x13, 0xE2, 0xFE, 0xF4]);
+    match m.run_for(1_000_000_000) {
+        RunExit::Halted { executed } => assert!(executed >= 5000, "{executed}"),
+        other => panic!("unexpected exit: {other:?}"),
+    }
+}
+
+#[test]
+fn run_until_deadline_polls_host_check_between_growing_batches() {
+    let mut m = new_machine(COMPUTE_LOOP);
+
+    let mut polls = 0u32;
+    let exit = m.run_until_deadline(&mut || {
+        polls += 1;
+        polls > 3
+    });
+
+    // Three batches ran before the fourth poll stopped the run: 1x, 2x and 4x the first batch.
+    assert_eq!(polls, 4);
+    assert_eq!(
+        exit,
+        RunExit::Completed {
+            executed: 7 * RUN_BUDGET_MIN_BATCH_INSTS
+        }
+    );
+
+    // A check that fires immediately runs nothing.
+    assert_eq!(
+        m.run_until_deadline(&mut || true),
+        RunExit::Completed { executed: 0 }
+    );
+}
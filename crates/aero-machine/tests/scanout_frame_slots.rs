@@ -0,0 +1,134 @@
+#![cfg(any(not(target_arch = "wasm32"), feature = "wasm-threaded"))]
+
+use std::sync::Arc;
+
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_shared::scanout_state::{
+    ScanoutFrameSlots, ScanoutState, SCANOUT_FORMAT_R8G8B8A8, SCANOUT_SOURCE_FRAME_SLOTS,
+    SCANOUT_SOURCE_LEGACY_TEXT,
+};
+use pretty_assertions::assert_eq;
+
+fn build_boot_sector(code: &[u8]) -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+/// Boots `code` with a shared scanout descriptor and a frame region of `region_bytes`.
+fn boot_with_frame_slots(
+    code: &[u8],
+    region_bytes: usize,
+) -> (Machine, Arc<ScanoutState>, Vec<u32>, ScanoutFrameSlots) {
+    let mut m = Machine::new(MachineConfig {
+        enable_pc_platform: true,
+        enable_vga: true,
+        enable_aerogpu: false,
+        // Keep the test output deterministic.
+        enable_serial: false,
+        enable_i8042: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let scanout_state = Arc::new(ScanoutState::new());
+    let mut region = vec![0u32; region_bytes / 4];
+    // Safety: the region is returned alongside the machine and kept alive for the whole test; the
+    // machine only writes it from `display_present`.
+    let slots =
+        unsafe { ScanoutFrameSlots::from_raw_parts(region.as_mut_ptr() as *mut u8, region_bytes) }
+            .unwrap();
+    m.set_scanout_state(Some(scanout_state.clone()));
+    m.set_scanout_frame_slots(Some(slots));
+
+    m.set_disk_image(build_boot_sector(code).to_vec()).unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+    (m, scanout_state, region, slots)
+}
+
+fn framebuffer_bytes(m: &Machine) -> Vec<u8> {
+    m.display_framebuffer()
+        .iter()
+        .flat_map(|px| px.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn display_present_flips_frames_through_double_buffered_slots() {
+    const TEXT_FRAME_BYTES: usize = 720 * 400 * 4;
+    // cli; hlt
+    let (mut m, scanout_state, _region, slots) =
+        boot_with_frame_slots(&[0xFA, 0xF4], 2 * TEXT_FRAME_BYTES + 128);
+    let mut out = Vec::new();
+
+    assert!(m.display_present(true));
+    let snap = slots.try_read_front(&scanout_state, &mut out).unwrap();
+    assert_eq!(snap.source, SCANOUT_SOURCE_FRAME_SLOTS);
+    assert_eq!(snap.format, SCANOUT_FORMAT_R8G8B8A8);
+    assert_eq!((snap.width, snap.height), m.display_resolution());
+    assert_eq!(snap.pitch_bytes, snap.width * 4);
+    assert!(snap.is_double_buffered());
+    assert_ne!(snap.slot0_offset, snap.slot1_offset);
+    assert_eq!(out, framebuffer_bytes(&m));
+    let (first_front, first_seq) = (snap.front_index, snap.frame_seq);
+
+    m.write_physical_u16(0xB8000, 0x1F41);
+    assert!(m.display_present(false));
+    let snap = slots.try_read_front(&scanout_state, &mut out).unwrap();
+    assert_eq!(snap.front_index, first_front ^ 1);
+    assert_eq!(snap.frame_seq, first_seq + 1);
+    assert_eq!(out, framebuffer_bytes(&m));
+
+    // Skipped presents do not publish a frame.
+    assert!(!m.display_present(false));
+    assert_eq!(scanout_state.snapshot().frame_seq, first_seq + 1);
+}
+
+#[test]
+fn small_frame_region_falls_back_to_single_buffer() {
+    const TEXT_FRAME_BYTES: usize = 720 * 400 * 4;
+    // cli; hlt
+    let (mut m, scanout_state, _region, slots) =
+        boot_with_frame_slots(&[0xFA, 0xF4], TEXT_FRAME_BYTES);
+    let mut out = Vec::new();
+
+    assert!(m.display_present(true));
+    let snap = slots.try_read_front(&scanout_state, &mut out).unwrap();
+    assert!(!snap.is_double_buffered());
+    assert_eq!(snap.slot0_offset, snap.slot1_offset);
+    assert_eq!(out, framebuffer_bytes(&m));
+}
+
+#[test]
+fn int10_mode_sets_do_not_publish_guest_descriptors_while_frame_slots_are_attached() {
+    const VBE_FRAME_BYTES: usize = 1024 * 768 * 4;
+    // mov ax, 0x4F02; mov bx, 0x4118 (1024x768x32 + LFB); int 0x10; cli; hlt
+    let code = [0xB8, 0x02, 0x4F, 0xBB, 0x18, 0x41, 0xCD, 0x10, 0xFA, 0xF4];
+    let (mut m, scanout_state, _region, slots) = boot_with_frame_slots(&code, 2 * VBE_FRAME_BYTES);
+
+    let snap = scanout_state.snapshot();
+    assert_eq!(snap.source, SCANOUT_SOURCE_LEGACY_TEXT);
+    assert_eq!(snap.generation, 0);
+
+    assert!(m.display_present(true));
+    let mut out = Vec::new();
+    let snap = slots.try_read_front(&scanout_state, &mut out).unwrap();
+    assert_eq!(snap.source, SCANOUT_SOURCE_FRAME_SLOTS);
+    assert_eq!((snap.width, snap.height), (1024, 768));
+    assert!(snap.is_double_buffered());
+    assert_eq!(out, framebuffer_bytes(&m));
+}
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_machine::{Machine, MachineConfig, MachineError, RunExit};
+use pretty_assertions::assert_eq;
+
+const COM2: u16 = 0x2F8;
+const COM4: u16 = 0x2E8;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn new_machine(code: &[u8]) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_com2: true,
+        enable_com4: true,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(code)).unwrap();
+    m.reset();
+    m
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+// mov dx, 0x2F8; mov al, 'B'; out dx, al; mov dx, 0x2E8; mov al, 'D'; out dx, al; cli; hlt
+const WRITE_COM2_COM4: [u8; 14] = [
+    0xBA, 0xF8, 0x02, 0xB0, b'B', 0xEE, 0xBA, 0xE8, 0x02, 0xB0, b'D', 0xEE, 0xFA, 0xF4,
+];
+
+#[test]
+fn secondary_ports_have_separate_logs_and_bda_entries() {
+    let mut m = new_machine(&WRITE_COM2_COM4);
+    run_until_halt(&mut m);
+
+    assert_eq!(m.take_serial_port_output(1), b"B");
+    assert_eq!(m.take_serial_port_output(3), b"D");
+    assert_eq!(m.serial_port_output_len(2), 0);
+    assert!(!m.take_serial_output().contains(&b'B'));
+
+    // BDA COM port table (0x40:0x00) and equipment word serial count (bits 9-11).
+    assert_eq!(m.read_physical_u16(0x400), 0x3F8);
+    assert_eq!(m.read_physical_u16(0x402), COM2);
+    assert_eq!(m.read_physical_u16(0x404), COM4);
+    assert_eq!(m.read_physical_u16(0x406), 0);
+    assert_eq!((m.read_physical_u16(0x410) >> 9) & 0x7, 3);
+
+    // Disabled ports float and reject input.
+    assert_eq!(m.io_read(0x3E8 + 5, 1), 0xFF);
+    assert!(!m.serial_write_input(2, b"x"));
+    assert!(m.serial_channel(2).is_none());
+}
+
+#[test]
+fn shared_irq3_is_wired_or() {
+    // cli; jmp $
+    let mut m = new_machine(&[0xFA, 0xEB, 0xFE]);
+    let interrupts = m.platform_interrupts().unwrap();
+    for base in [COM2, COM4] {
+        m.io_write(base + 4, 1, 0x08); // MCR.OUT2
+        m.io_write(base + 1, 1, 0x01); // IER: received data available
+    }
+    assert!(m.serial_write_input(1, b"a"));
+    assert!(m.serial_write_input(3, b"b"));
+    m.run_slice(100);
+    assert!(interrupts.borrow().gsi_level(3));
+
+    // Draining COM2 leaves COM4 holding the shared line.
+    assert_eq!(m.io_read(COM2, 1), u32::from(b'a'));
+    m.run_slice(100);
+    assert!(interrupts.borrow().gsi_level(3));
+
+    assert_eq!(m.io_read(COM4, 1), u32::from(b'b'));
+    m.run_slice(100);
+    assert!(!interrupts.borrow().gsi_level(3));
+}
+
+#[test]
+fn serial_channel_carries_both_directions() {
+    let mut m = new_machine(&WRITE_COM2_COM4);
+    let channel = m.serial_channel(1).unwrap();
+    run_until_halt(&mut m);
+    m.take_serial_port_output(0);
+
+    assert_eq!(channel.recv(), b"B");
+    assert_eq!(m.serial_port_output_len(1), 0);
+    assert_eq!(m.take_serial_port_output(3), b"D");
+
+    channel.send(b"kd");
+    m.run_slice(1);
+    assert_eq!(m.io_read(COM2 + 5, 1) & 0x01, 0x01);
+    assert_eq!(m.io_read(COM2, 1), u32::from(b'k'));
+    assert_eq!(m.io_read(COM2, 1), u32::from(b'd'));
+
+    // Detaching resumes logging; the channel survives reset while attached.
+    let again = m.serial_channel(1).unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+    assert_eq!(again.recv(), b"B");
+    m.detach_serial_channel(1);
+    m.reset();
+    run_until_halt(&mut m);
+    assert_eq!(m.take_serial_port_output(1), b"B");
+}
+
+#[test]
+fn snapshot_preserves_all_port_logs() {
+    let mut m = new_machine(&WRITE_COM2_COM4);
+    run_until_halt(&mut m);
+    let snap = m.take_snapshot_full().unwrap();
+
+    m.take_serial_port_output(1);
+    m.take_serial_port_output(3);
+    m.restore_snapshot_bytes(&snap).unwrap();
+    assert_eq!(m.take_serial_port_output(1), b"B");
+    assert_eq!(m.take_serial_port_output(3), b"D");
+}
+
+#[test]
+fn invalid_serial_irq_is_rejected_for_enabled_ports() {
+    let cfg = MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_com2: true,
+        serial_irqs: [4, 2, 16, 3],
+        ..Default::default()
+    };
+    assert_eq!(
+        Machine::new(cfg).err(),
+        Some(MachineError::InvalidSerialIrq { port: 1, irq: 2 })
+    );
+}
+
+#[test]
+fn sinks_fan_out_output_alongside_a_bounded_log() {
+    let mut m = new_machine(&WRITE_COM2_COM4);
+    let first = Rc::new(RefCell::new(Vec::new()));
+    let second = Rc::new(RefCell::new(Vec::new()));
+    let sink = |out: &Rc<RefCell<Vec<u8>>>| {
+        let out = out.clone();
+        Box::new(move |bytes: &[u8]| out.borrow_mut().extend_from_slice(bytes))
+    };
+    let first_id = m.add_serial_sink(1, sink(&first)).unwrap();
+    let second_id = m.add_serial_sink(1, sink(&second)).unwrap();
+    assert!(m.add_serial_sink(2, sink(&first)).is_none());
+    assert_ne!(first_id, second_id);
+
+    m.set_serial_log_capacity(Some(4));
+    run_until_halt(&mut m);
+    // Output is delivered when the slice returns, without polling a getter.
+    assert_eq!(*first.borrow(), b"B");
+    assert_eq!(*second.borrow(), b"B");
+
+    for byte in *b"123456" {
+        m.io_write(COM2, 1, u32::from(byte));
+    }
+    assert_eq!(m.serial_port_output_len(1), 4);
+    assert_eq!(m.take_serial_port_output(1), b"3456");
+    assert_eq!(*first.borrow(), b"B123456");
+
+    // Sinks survive reset, see bytes routed to a channel, and stop once removed.
+    let channel = m.serial_channel(1).unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+    assert_eq!(channel.recv(), b"B");
+    assert!(m.remove_serial_sink(first_id));
+    assert!(!m.remove_serial_sink(first_id));
+    m.io_write(COM2, 1, u32::from(b'!'));
+    m.run_slice(1);
+    assert_eq!(*first.borrow(), b"B123456B");
+    assert_eq!(*second.borrow(), b"B123456B!");
+    assert_eq!(Rc::strong_count(&first), 1, "removed sinks are dropped");
+}
@@ -0,0 +1,290 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices_storage::atapi::{AtapiCdrom, IsoBackend, PacketResult};
+use aero_machine::{Machine, MachineConfig, RunExit, SharedIsoDisk};
+use aero_storage::{DiskError, VirtualDisk};
+use pretty_assertions::assert_eq;
+
+const ISO_BLOCK_BYTES: usize = 2048;
+const ISO_CAPACITY_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+const ISO_SECTORS: u64 = ISO_CAPACITY_BYTES / ISO_BLOCK_BYTES as u64;
+/// Last 2048-byte sector that lies entirely below the 4 GiB byte offset.
+const LBA_BELOW_4GIB: u32 = (1 << 21) - 1;
+const LBA_AT_4GIB: u32 = 1 << 21;
+const LAST_LBA: u32 = (ISO_SECTORS - 1) as u32;
+
+const BOOT_CATALOG_LBA: usize = 20;
+const BOOT_IMAGE_LBA: usize = 21;
+/// Blocks covered by the explicit El Torito layout (and by the ISO9660 volume space size).
+const HEAD_BLOCKS: usize = 32;
+
+/// Sparse 5 GiB ISO: an explicit head (volume descriptors, boot catalog, boot image) followed by
+/// synthetic sectors whose every 8-byte word is the sector's own LBA. A 32-bit truncation anywhere
+/// in the sector or byte-offset math shows up as the wrong LBA in the data.
+struct SparseIso {
+    head: Vec<u8>,
+}
+
+impl VirtualDisk for SparseIso {
+    fn capacity_bytes(&self) -> u64 {
+        ISO_CAPACITY_BYTES
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> aero_storage::Result<()> {
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        if end > ISO_CAPACITY_BYTES {
+            return Err(DiskError::OutOfBounds {
+                offset,
+                len: buf.len(),
+                capacity: ISO_CAPACITY_BYTES,
+            });
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let pos = offset + i as u64;
+            *byte = if pos < self.head.len() as u64 {
+                self.head[pos as usize]
+            } else {
+                let lba = pos / ISO_BLOCK_BYTES as u64;
+                lba.to_le_bytes()[(pos % 8) as usize]
+            };
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, _offset: u64, _buf: &[u8]) -> aero_storage::Result<()> {
+        Err(DiskError::NotSupported("read-only test ISO".to_string()))
+    }
+
+    fn flush(&mut self) -> aero_storage::Result<()> {
+        Ok(())
+    }
+}
+
+fn iso_block_mut(img: &mut [u8], lba: usize) -> &mut [u8] {
+    &mut img[lba * ISO_BLOCK_BYTES..(lba + 1) * ISO_BLOCK_BYTES]
+}
+
+fn volume_descriptor(img: &mut [u8], lba: usize, ty: u8) -> &mut [u8] {
+    let block = iso_block_mut(img, lba);
+    block[0] = ty;
+    block[1..6].copy_from_slice(b"CD001");
+    block[6] = 1;
+    block
+}
+
+/// El Torito no-emulation layout. Like a UDF-bridge disc, the ISO9660 primary volume descriptor
+/// only covers the first [`HEAD_BLOCKS`] sectors of the image.
+fn build_head(boot_image: &[u8; ISO_BLOCK_BYTES]) -> Vec<u8> {
+    let mut img = vec![0u8; HEAD_BLOCKS * ISO_BLOCK_BYTES];
+
+    let pvd = volume_descriptor(&mut img, 16, 0x01);
+    // Volume space size (both-endian u32).
+    pvd[80..84].copy_from_slice(&(HEAD_BLOCKS as u32).to_le_bytes());
+    pvd[84..88].copy_from_slice(&(HEAD_BLOCKS as u32).to_be_bytes());
+
+    let brvd = volume_descriptor(&mut img, 17, 0x00);
+    let system_id = b"EL TORITO SPECIFICATION";
+    brvd[7..39].fill(b' ');
+    brvd[7..7 + system_id.len()].copy_from_slice(system_id);
+    brvd[0x47..0x4B].copy_from_slice(&(BOOT_CATALOG_LBA as u32).to_le_bytes());
+
+    volume_descriptor(&mut img, 18, 0xFF);
+
+    let catalog = iso_block_mut(&mut img, BOOT_CATALOG_LBA);
+    catalog[0] = 0x01; // validation entry header id
+    catalog[0x1E] = 0x55;
+    catalog[0x1F] = 0xAA;
+    let sum = catalog[..32].chunks_exact(2).fold(0u16, |sum, w| {
+        sum.wrapping_add(u16::from_le_bytes([w[0], w[1]]))
+    });
+    catalog[0x1C..0x1E].copy_from_slice(&0u16.wrapping_sub(sum).to_le_bytes());
+    catalog[32] = 0x88; // bootable
+    catalog[33] = 0x00; // no emulation
+    catalog[34..36].copy_from_slice(&0x07C0u16.to_le_bytes()); // load segment
+    catalog[38..40].copy_from_slice(&4u16.to_le_bytes()); // 512-byte sectors to load
+    catalog[40..44].copy_from_slice(&(BOOT_IMAGE_LBA as u32).to_le_bytes());
+
+    iso_block_mut(&mut img, BOOT_IMAGE_LBA).copy_from_slice(boot_image);
+    img
+}
+
+fn sparse_iso() -> SharedIsoDisk {
+    let head = build_head(&[0u8; ISO_BLOCK_BYTES]);
+    SharedIsoDisk::new(Box::new(SparseIso { head })).unwrap()
+}
+
+fn expected_sector(lba: u32) -> Vec<u8> {
+    u64::from(lba).to_le_bytes().repeat(ISO_BLOCK_BYTES / 8)
+}
+
+fn read_10(lba: u32, blocks: u16) -> [u8; 12] {
+    let mut pkt = [0u8; 12];
+    pkt[0] = 0x28;
+    pkt[2..6].copy_from_slice(&lba.to_be_bytes());
+    pkt[7..9].copy_from_slice(&blocks.to_be_bytes());
+    pkt
+}
+
+fn read_12(lba: u32, blocks: u32) -> [u8; 12] {
+    let mut pkt = [0u8; 12];
+    pkt[0] = 0xA8;
+    pkt[2..6].copy_from_slice(&lba.to_be_bytes());
+    pkt[6..10].copy_from_slice(&blocks.to_be_bytes());
+    pkt
+}
+
+fn data_in(result: PacketResult) -> Vec<u8> {
+    match result {
+        PacketResult::DataIn(data) => data,
+        other => panic!("expected DataIn, got {other:?}"),
+    }
+}
+
+#[test]
+fn shared_iso_reports_64bit_sector_count() {
+    let iso = sparse_iso();
+    assert_eq!(IsoBackend::sector_count(&iso), ISO_SECTORS);
+    assert_eq!(
+        firmware::bios::CdromDevice::size_in_sectors(&iso),
+        ISO_SECTORS
+    );
+}
+
+#[test]
+fn atapi_reads_sectors_around_and_beyond_4gib() {
+    let mut cd = AtapiCdrom::new(Some(Box::new(sparse_iso())));
+    let tur = [0u8; 12];
+    // The first command after insertion reports UNIT ATTENTION (medium changed).
+    let _ = cd.handle_packet(&tur, false);
+
+    // READ CAPACITY(10) follows the backend size, not the ISO9660 volume space size.
+    let mut pkt = [0u8; 12];
+    pkt[0] = 0x25;
+    let capacity = data_in(cd.handle_packet(&pkt, false));
+    assert_eq!(&capacity[0..4], &LAST_LBA.to_be_bytes());
+    assert_eq!(&capacity[4..8], &(ISO_BLOCK_BYTES as u32).to_be_bytes());
+
+    // A READ(10) straddling the 4 GiB byte offset.
+    let data = data_in(cd.handle_packet(&read_10(LBA_BELOW_4GIB, 2), false));
+    assert_eq!(data.len(), 2 * ISO_BLOCK_BYTES);
+    assert_eq!(
+        &data[..ISO_BLOCK_BYTES],
+        &expected_sector(LBA_BELOW_4GIB)[..]
+    );
+    assert_eq!(&data[ISO_BLOCK_BYTES..], &expected_sector(LBA_AT_4GIB)[..]);
+
+    // READ(12) of the last sector, via DMA.
+    match cd.handle_packet(&read_12(LAST_LBA, 1), true) {
+        PacketResult::DmaIn(data) => assert_eq!(data, expected_sector(LAST_LBA)),
+        other => panic!("expected DmaIn, got {other:?}"),
+    }
+
+    // Past the end of the medium: ILLEGAL REQUEST / LBA OUT OF RANGE.
+    for pkt in [read_10(LAST_LBA, 2), read_12(LAST_LBA + 1, 1)] {
+        match cd.handle_packet(&pkt, false) {
+            PacketResult::Error { sense_key, asc, .. } => {
+                assert_eq!((sense_key, asc), (0x05, 0x21))
+            }
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+}
+
+fn rel8(from_next: usize, to: usize) -> u8 {
+    let diff = to as isize - from_next as isize;
+    assert!((-128..=127).contains(&diff), "rel8 out of range");
+    diff as i8 as u8
+}
+
+/// Boot image that reads each of `lbas` with INT 13h AH=42h from the CD boot drive and checks the
+/// low dword of the synthetic data, then checks that a read past the end fails. Writes `S` or `F`
+/// to COM1 and halts.
+fn build_boot_image(lbas: &[u32], lba_past_end: u32) -> [u8; ISO_BLOCK_BYTES] {
+    const BUF: u16 = 0x0500;
+
+    let mut code: Vec<u8> = vec![0x31, 0xC0, 0x8E, 0xD8]; // xor ax, ax; mov ds, ax
+    let mut dap_fixups = Vec::new();
+    let mut fail_fixups = Vec::new();
+
+    let mut int13_read = |code: &mut Vec<u8>| {
+        code.push(0xBE); // mov si, imm16 (DAP address, patched below)
+        dap_fixups.push(code.len());
+        code.extend_from_slice(&[0, 0]);
+        code.extend_from_slice(&[0xB2, 0xE0]); // mov dl, 0xE0
+        code.extend_from_slice(&[0xB4, 0x42]); // mov ah, 0x42
+        code.extend_from_slice(&[0xCD, 0x13]); // int 0x13
+    };
+
+    for &lba in lbas {
+        int13_read(&mut code);
+        code.push(0x72); // jc fail
+        fail_fixups.push(code.len());
+        code.push(0);
+        for (offset, word) in [(0u16, lba as u16), (2, (lba >> 16) as u16)] {
+            // cmp word [BUF + offset], imm16
+            code.extend_from_slice(&[0x81, 0x3E]);
+            code.extend_from_slice(&(BUF + offset).to_le_bytes());
+            code.extend_from_slice(&word.to_le_bytes());
+            code.push(0x75); // jne fail
+            fail_fixups.push(code.len());
+            code.push(0);
+        }
+    }
+    int13_read(&mut code);
+    code.push(0x73); // jnc fail
+    fail_fixups.push(code.len());
+    code.push(0);
+
+    for (i, status) in [b'S', b'F'].into_iter().enumerate() {
+        if i == 1 {
+            for &pos in &fail_fixups {
+                code[pos] = rel8(pos + 1, code.len());
+            }
+        }
+        code.extend_from_slice(&[0xBA, 0xF8, 0x03]); // mov dx, 0x3f8
+        code.extend_from_slice(&[0xB0, status]); // mov al, status
+        code.extend_from_slice(&[0xEE, 0xFA, 0xF4]); // out dx, al; cli; hlt
+    }
+
+    for (&pos, lba) in dap_fixups
+        .iter()
+        .zip(lbas.iter().copied().chain([lba_past_end]))
+    {
+        let dap_addr = 0x7C00 + code.len() as u16;
+        code[pos..pos + 2].copy_from_slice(&dap_addr.to_le_bytes());
+        code.extend_from_slice(&[0x10, 0x00]); // size, reserved
+        code.extend_from_slice(&1u16.to_le_bytes()); // one 2048-byte sector
+        code.extend_from_slice(&BUF.to_le_bytes()); // offset
+        code.extend_from_slice(&0u16.to_le_bytes()); // segment
+        code.extend_from_slice(&u64::from(lba).to_le_bytes());
+    }
+
+    let mut img = [0u8; ISO_BLOCK_BYTES];
+    img[..code.len()].copy_from_slice(&code);
+    img
+}
+
+#[test]
+fn int13_cd_reads_sectors_around_and_beyond_4gib() {
+    let boot_image = build_boot_image(&[LBA_BELOW_4GIB, LBA_AT_4GIB, LAST_LBA], LAST_LBA + 1);
+    let iso = SparseIso {
+        head: build_head(&boot_image),
+    };
+
+    let mut m = Machine::new(MachineConfig::win7_storage(16 * 1024 * 1024)).unwrap();
+    m.attach_install_media_iso(Box::new(iso)).unwrap();
+    m.set_boot_drive(0xE0);
+    m.reset();
+    assert_eq!(m.cpu().segments.cs.selector, 0x07C0);
+
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => break,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    assert_eq!(m.take_serial_output(), vec![b'S']);
+}
@@ -0,0 +1,326 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::profile::AEROGPU_BAR0_INDEX;
+use aero_devices::pic8259::{MASTER_CMD, MASTER_DATA, SLAVE_CMD, SLAVE_DATA};
+use aero_devices::pit8254::{PIT_CH0, PIT_CMD};
+use aero_devices_storage::pci_ide::PRIMARY_PORTS;
+use aero_machine::{
+    Machine, MachineConfig, RunExit, SnapshotProbeRun, StateDivergence, AEROGPU_VRAM_SIZE_MIN,
+};
+use aero_protocol::aerogpu::aerogpu_pci as pci;
+use aero_storage::{MemBackend, RawDisk, VirtualDisk as _, SECTOR_SIZE};
+
+const HANDLER_ADDR: u16 = 0x0800;
+const CODE_ADDR: u16 = 0x1000;
+const TICKS_ADDR: u64 = 0x0600;
+const CHECKSUM_ADDR: u64 = 0x0602;
+const SECTOR_BUF: u16 = 0x4000;
+const DISK_SECTORS: u64 = 8;
+const COM1: u16 = 0x3F8;
+
+/// Instructions executed by each probe run.
+const RUN_INSTS: u64 = 60_000;
+const SEEDS: u64 = 4;
+
+/// Small deterministic PRNG so failures reproduce from the printed seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        lo + self.next() % (hi - lo)
+    }
+}
+
+fn cfg() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_ide: true,
+        enable_aerogpu: true,
+        aerogpu_vram_size_bytes: Some(AEROGPU_VRAM_SIZE_MIN),
+        enable_serial: true,
+        enable_vga: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    }
+}
+
+fn disk() -> Box<RawDisk<MemBackend>> {
+    let mut disk = RawDisk::create(MemBackend::new(), DISK_SECTORS * SECTOR_SIZE as u64).unwrap();
+    for lba in 0..DISK_SECTORS {
+        let sector: Vec<u8> = (0..SECTOR_SIZE)
+            .map(|i| (i as u64 * 7 + lba * 31) as u8)
+            .collect();
+        disk.write_sectors(lba, &sector).unwrap();
+    }
+    Box::new(disk)
+}
+
+/// IRQ0 handler: bump the tick counter, print a letter on COM1 and EOI the master PIC.
+fn timer_handler() -> Vec<u8> {
+    let mut code = Vec::new();
+    code.extend_from_slice(&[0x50, 0x52]); // push ax; push dx
+    code.extend_from_slice(&[0xFF, 0x06, 0x00, 0x06]); // inc word [0x0600]
+    code.extend_from_slice(&[0xA0, 0x00, 0x06]); // mov al, [0x0600]
+    code.extend_from_slice(&[0x24, 0x0F, 0x04, b'A']); // and al, 0x0f; add al, 'A'
+    code.push(0xBA); // mov dx, COM1
+    code.extend_from_slice(&COM1.to_le_bytes());
+    code.push(0xEE); // out dx, al
+    code.extend_from_slice(&[0xB0, 0x20, 0xE6, MASTER_CMD as u8]); // mov al, 0x20; out 0x20, al
+    code.extend_from_slice(&[0x5A, 0x58, 0xCF]); // pop dx; pop ax; iret
+    code
+}
+
+fn out_dx_imm8(code: &mut Vec<u8>, port: u16, value: u8) {
+    code.push(0xBA); // mov dx, imm16
+    code.extend_from_slice(&port.to_le_bytes());
+    code.extend_from_slice(&[0xB0, value, 0xEE]); // mov al, imm8; out dx, al
+}
+
+fn mov_fs_ebp_disp32_eax(code: &mut Vec<u8>, reg: u32) {
+    code.extend_from_slice(&[0x64, 0x67, 0x66, 0x89, 0x85]); // mov fs:[ebp+disp32], eax
+    code.extend_from_slice(&reg.to_le_bytes());
+}
+
+/// Main loop: PIO-read sector `bx` from the IDE primary master, program the AeroGPU scanout
+/// registers from its contents (FB_GPA as a LO/HI pair), fold it into a RAM checksum and print a
+/// marker on COM1.
+fn main_loop(bar0: u32) -> Vec<u8> {
+    let cmd = PRIMARY_PORTS.cmd_base;
+    let mut code = Vec::new();
+    code.extend_from_slice(&[0x66, 0xBD]); // mov ebp, bar0
+    code.extend_from_slice(&bar0.to_le_bytes());
+    code.extend_from_slice(&[0x31, 0xDB]); // xor bx, bx
+    code.push(0xFC); // cld
+
+    let top = code.len();
+    out_dx_imm8(&mut code, cmd + 2, 1); // sector count
+    code.push(0xBA); // mov dx, cmd+3
+    code.extend_from_slice(&(cmd + 3).to_le_bytes());
+    code.extend_from_slice(&[0x88, 0xD8, 0xEE]); // mov al, bl; out dx, al (LBA 7:0)
+    out_dx_imm8(&mut code, cmd + 4, 0);
+    out_dx_imm8(&mut code, cmd + 5, 0);
+    out_dx_imm8(&mut code, cmd + 6, 0xE0);
+    out_dx_imm8(&mut code, cmd + 7, 0x20); // READ SECTORS
+
+    // Poll for !BSY && DRQ.
+    let poll = code.len();
+    code.extend_from_slice(&[0xEC, 0xA8, 0x80]); // in al, dx; test al, 0x80
+    code.extend_from_slice(&[0x75, (poll as i32 - (code.len() as i32 + 2)) as u8]); // jnz poll
+    code.extend_from_slice(&[0xA8, 0x08]); // test al, 0x08
+    code.extend_from_slice(&[0x74, (poll as i32 - (code.len() as i32 + 2)) as u8]); // jz poll
+
+    code.push(0xBA); // mov dx, cmd
+    code.extend_from_slice(&cmd.to_le_bytes());
+    code.push(0xBF); // mov di, SECTOR_BUF
+    code.extend_from_slice(&SECTOR_BUF.to_le_bytes());
+    code.extend_from_slice(&[0xB9, 0x00, 0x01]); // mov cx, 256
+    code.extend_from_slice(&[0xF3, 0x6D]); // rep insw
+
+    code.push(0xA1); // mov ax, [SECTOR_BUF]
+    code.extend_from_slice(&SECTOR_BUF.to_le_bytes());
+    code.extend_from_slice(&[0x01, 0x06]); // add [CHECKSUM_ADDR], ax
+    code.extend_from_slice(&(CHECKSUM_ADDR as u16).to_le_bytes());
+
+    code.extend_from_slice(&[0x66, 0xA1]); // mov eax, [SECTOR_BUF]
+    code.extend_from_slice(&SECTOR_BUF.to_le_bytes());
+    mov_fs_ebp_disp32_eax(&mut code, pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH);
+    mov_fs_ebp_disp32_eax(&mut code, pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_LO);
+    code.extend_from_slice(&[0x66, 0x31, 0xC0]); // xor eax, eax
+    mov_fs_ebp_disp32_eax(&mut code, pci::AEROGPU_MMIO_REG_SCANOUT0_FB_GPA_HI);
+
+    out_dx_imm8(&mut code, COM1, b'.');
+
+    code.extend_from_slice(&[0x43, 0x83, 0xE3, (DISK_SECTORS - 1) as u8]); // inc bx; and bx, 7
+    code.push(0xE9); // jmp top
+    let rel = top as i32 - (code.len() as i32 + 2);
+    code.extend_from_slice(&(rel as i16).to_le_bytes());
+    code
+}
+
+/// Build the workload machine: a real-mode guest with IRQ0 driven by the PIT and `fs` set up as an
+/// unreal-mode flat segment so it can reach AeroGPU BAR0.
+fn workload_machine() -> Machine {
+    let mut m = Machine::new(cfg()).unwrap();
+    m.attach_ide_primary_master_disk(disk()).unwrap();
+
+    let bdf = m.aerogpu_bdf().expect("AeroGPU device should be present");
+    let bar0 = m
+        .pci_bar_base(bdf, AEROGPU_BAR0_INDEX)
+        .expect("AeroGPU BAR0 should be mapped");
+
+    m.write_physical(u64::from(HANDLER_ADDR), &timer_handler());
+    m.write_physical(u64::from(CODE_ADDR), &main_loop(bar0 as u32));
+    // IVT[0x08] = 0000:HANDLER_ADDR
+    m.write_physical_u16(0x08 * 4, HANDLER_ADDR);
+    m.write_physical_u16(0x08 * 4 + 2, 0);
+
+    // PIC: master at 0x08 with only IRQ0 unmasked, slave at 0x70 fully masked.
+    for (port, value) in [
+        (MASTER_CMD, 0x11),
+        (SLAVE_CMD, 0x11),
+        (MASTER_DATA, 0x08),
+        (SLAVE_DATA, 0x70),
+        (MASTER_DATA, 0x04),
+        (SLAVE_DATA, 0x02),
+        (MASTER_DATA, 0x01),
+        (SLAVE_DATA, 0x01),
+        (MASTER_DATA, 0xFE),
+        (SLAVE_DATA, 0xFF),
+    ] {
+        m.io_write(port, 1, value);
+    }
+    // PIT channel 0: mode 2, lobyte/hibyte.
+    m.io_write(PIT_CMD, 1, 0x34);
+    m.io_write(PIT_CH0, 1, 0x02);
+    m.io_write(PIT_CH0, 1, 0x00);
+
+    let cpu = m.cpu_mut();
+    for seg in [
+        &mut cpu.segments.cs,
+        &mut cpu.segments.ds,
+        &mut cpu.segments.es,
+        &mut cpu.segments.ss,
+        &mut cpu.segments.fs,
+        &mut cpu.segments.gs,
+    ] {
+        seg.selector = 0;
+        seg.base = 0;
+        seg.limit = 0xFFFF;
+        seg.access = 0;
+    }
+    cpu.segments.fs.limit = 0xFFFF_FFFF;
+    cpu.set_stack_ptr(0x7000);
+    cpu.set_rip(u64::from(CODE_ADDR));
+    cpu.set_rflags(0x202); // IF=1
+    cpu.halted = false;
+
+    // Drop firmware output from `Machine::new` so serial only carries workload output.
+    m.take_serial_output();
+    m
+}
+
+/// Restore `snapshot` into a fresh machine, re-attaching the (read-only) disk backend as hosts do.
+fn restored_machine(snapshot: &[u8]) -> Machine {
+    let mut m = Machine::new(cfg()).unwrap();
+    m.restore_snapshot_bytes(snapshot).unwrap();
+    m.attach_ide_primary_master_disk(disk()).unwrap();
+    m
+}
+
+fn probe_run(m: &mut Machine, max_insts: u64, probe_every: u64) -> SnapshotProbeRun {
+    let run = m
+        .run_slice_with_snapshot_probe(max_insts, probe_every)
+        .unwrap();
+    assert!(
+        matches!(run.exit, RunExit::Completed { .. }),
+        "unexpected exit: {:?}",
+        run.exit
+    );
+    run
+}
+
+/// Restore probe `index` of `run` and continue with the same chunking; return the first probe
+/// (in `run` order) whose fingerprint the restored machine did not reproduce.
+fn first_divergence(
+    run: &SnapshotProbeRun,
+    index: usize,
+    probe_every: u64,
+) -> Option<(usize, Vec<StateDivergence>)> {
+    let start = &run.probes[index];
+    let mut restored = restored_machine(&start.snapshot);
+    let rerun = probe_run(&mut restored, RUN_INSTS - start.executed, probe_every);
+    assert_eq!(rerun.probes.len(), run.probes.len() - index - 1);
+    rerun
+        .probes
+        .iter()
+        .zip(&run.probes[index + 1..])
+        .enumerate()
+        .find_map(|(i, (ours, theirs))| {
+            let diff = theirs.fingerprint.diff(&ours.fingerprint);
+            (!diff.is_empty()).then_some((index + 1 + i, diff))
+        })
+}
+
+#[test]
+fn snapshot_probe_workload_exercises_disk_timer_and_aerogpu() {
+    let mut m = workload_machine();
+    let run = probe_run(&mut m, RUN_INSTS, 10_000);
+    assert_eq!(run.exit.executed(), RUN_INSTS);
+    assert_eq!(run.probes.len(), 6);
+    assert_eq!(run.probes.last().unwrap().executed, RUN_INSTS);
+
+    let serial: Vec<u8> = run
+        .probes
+        .iter()
+        .flat_map(|probe| probe.fingerprint.serial.iter().copied())
+        .collect();
+    assert!(
+        serial.iter().filter(|&&b| b == b'.').count() > DISK_SECTORS as usize,
+        "main loop should read every sector at least once"
+    );
+    assert!(
+        serial.iter().any(u8::is_ascii_uppercase),
+        "PIT IRQ0 handler should have run"
+    );
+    assert!(m.read_physical_u16(TICKS_ADDR) >= 2);
+
+    let bdf = m.aerogpu_bdf().unwrap();
+    let bar0 = m.pci_bar_base(bdf, AEROGPU_BAR0_INDEX).unwrap();
+    assert_ne!(
+        m.read_physical_u32(bar0 + u64::from(pci::AEROGPU_MMIO_REG_SCANOUT0_WIDTH)),
+        0
+    );
+}
+
+#[test]
+fn snapshot_probe_restore_at_random_boundaries_is_deterministic() {
+    for seed in 0..SEEDS {
+        let mut rng = SplitMix64(seed);
+        let probe_every = rng.range(2_500, 12_000);
+        let mut m = workload_machine();
+        let run = probe_run(&mut m, RUN_INSTS, probe_every);
+
+        let index = rng.range(0, run.probes.len() as u64 - 1) as usize;
+        if let Some((probe, diff)) = first_divergence(&run, index, probe_every) {
+            let report: Vec<String> = diff.iter().map(ToString::to_string).collect();
+            panic!(
+                "seed {seed}: restore at {} insts (chunk {probe_every}) diverged by {} insts: {}",
+                run.probes[index].executed,
+                run.probes[probe].executed,
+                report.join("; ")
+            );
+        }
+    }
+}
+
+#[test]
+fn snapshot_probe_reports_the_differing_section() {
+    let mut m = workload_machine();
+    let run = probe_run(&mut m, 20_000, 5_000);
+
+    let mut restored = restored_machine(&run.probes[1].snapshot);
+    restored.write_physical_u8(0x0010_0000, 0xAA);
+    let rerun = probe_run(&mut restored, 5_000, 5_000);
+
+    let diff = run.probes[2].fingerprint.diff(&rerun.probes[0].fingerprint);
+    assert_eq!(
+        diff,
+        vec![StateDivergence::Ram {
+            first_offset: 0x0010_0000,
+            pages: 1
+        }]
+    );
+}
@@ -0,0 +1,104 @@
+use aero_machine::{Machine, MachineConfig};
+use aero_snapshot as snapshot;
+use pretty_assertions::assert_eq;
+use std::io::Cursor;
+
+const MARKER_ADDR: u64 = 0x10_0000;
+const KEY: u8 = 0x5A;
+
+fn minimal_machine_config() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+/// Incompressible marker so it would show up verbatim in an untransformed snapshot.
+fn marker() -> Vec<u8> {
+    let mut state = 0x1234_5678u32;
+    (0..256)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+fn xor(input: &[u8], out: &mut Vec<u8>) {
+    out.extend(input.iter().map(|b| b ^ KEY));
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn transformed_snapshot_roundtrips_without_plaintext_ram() {
+    let marker = marker();
+    let mut src = Machine::new(minimal_machine_config()).unwrap();
+    src.write_physical(MARKER_ADDR, &marker);
+
+    let plain = src.take_snapshot_full().unwrap();
+    assert!(contains(&plain, &marker));
+
+    let mut max_block = 0usize;
+    let mut encrypt = |input: &[u8], out: &mut Vec<u8>| {
+        max_block = max_block.max(input.len());
+        xor(input, out);
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    src.save_snapshot_with_transform(&mut cursor, b"key-id:1", &mut encrypt)
+        .unwrap();
+    let bytes = cursor.into_inner();
+    assert!(!contains(&bytes, &marker));
+    // The transform only ever sees one (compressed) RAM chunk at a time.
+    assert!(max_block <= 1024 * 1024 + 64 * 1024);
+
+    let index = snapshot::inspect_snapshot(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(
+        index.ram.unwrap().transform_metadata.as_deref(),
+        Some(&b"key-id:1"[..])
+    );
+
+    let mut dst = Machine::new(minimal_machine_config()).unwrap();
+    dst.restore_snapshot_with_transform(&mut Cursor::new(&bytes), &mut xor)
+        .unwrap();
+    assert_eq!(dst.read_physical_bytes(MARKER_ADDR, marker.len()), marker);
+}
+
+#[test]
+fn transformed_snapshot_requires_matching_restore_path() {
+    let mut src = Machine::new(minimal_machine_config()).unwrap();
+    let mut cursor = Cursor::new(Vec::new());
+    src.save_snapshot_with_transform(&mut cursor, &[], &mut xor)
+        .unwrap();
+    let transformed = cursor.into_inner();
+    let plain = src.take_snapshot_full().unwrap();
+
+    let mut dst = Machine::new(minimal_machine_config()).unwrap();
+    let err = dst.restore_snapshot_bytes(&transformed).unwrap_err();
+    assert!(matches!(
+        err,
+        snapshot::SnapshotError::RamTransformMismatch {
+            transformed: true,
+            supplied: false
+        }
+    ));
+
+    let mut dst = Machine::new(minimal_machine_config()).unwrap();
+    let err = dst
+        .restore_snapshot_with_transform(&mut Cursor::new(&plain), &mut xor)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        snapshot::SnapshotError::RamTransformMismatch {
+            transformed: false,
+            supplied: true
+        }
+    ));
+}
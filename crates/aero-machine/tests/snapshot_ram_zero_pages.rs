@@ -0,0 +1,77 @@
+use aero_machine::{Machine, MachineConfig};
+use aero_snapshot as snapshot;
+use pretty_assertions::assert_eq;
+use std::io::Cursor;
+
+const RAM_SIZE: u64 = 64 * 1024 * 1024;
+const MARKER_ADDR: u64 = 0x20_0000;
+const ZERO_ADDR: u64 = 0x30_0000;
+
+fn minimal_machine_config() -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: RAM_SIZE,
+        enable_pc_platform: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    }
+}
+
+fn save(m: &mut Machine, zero_pages: bool) -> (Vec<u8>, snapshot::RamSectionStats) {
+    let mut options = snapshot::SaveOptions::default();
+    options.ram.zero_pages = zero_pages;
+    let mut cursor = Cursor::new(Vec::new());
+    let stats = m
+        .save_snapshot_with_options_to(&mut cursor, options)
+        .unwrap();
+    (cursor.into_inner(), stats)
+}
+
+#[test]
+fn zero_pages_shrink_idle_guest_snapshot() {
+    let mut src = Machine::new(minimal_machine_config()).unwrap();
+    src.write_physical(MARKER_ADDR, b"aero zero-page snapshot marker");
+
+    let (bytes, stats) = save(&mut src, true);
+    assert_eq!(stats.raw_len, RAM_SIZE);
+    assert!(stats.zero_len > RAM_SIZE / 2);
+    assert!(stats.stored_len <= bytes.len() as u64);
+    let ratio = stats.raw_len / stats.stored_len;
+    assert!(ratio >= 100, "compression ratio {ratio} too low");
+
+    let (_, without) = save(&mut src, false);
+    assert_eq!(without.zero_len, 0);
+    assert!(stats.stored_len < without.stored_len);
+
+    // Stale data in the restore target must be overwritten by zero-page runs.
+    let mut dst = Machine::new(minimal_machine_config()).unwrap();
+    dst.write_physical(ZERO_ADDR, &[0xEE; 64]);
+    dst.restore_snapshot_bytes(&bytes).unwrap();
+    assert_eq!(
+        dst.read_physical_bytes(MARKER_ADDR, 30),
+        b"aero zero-page snapshot marker"
+    );
+    assert_eq!(dst.read_physical_bytes(ZERO_ADDR, 64), vec![0; 64]);
+}
+
+#[test]
+fn snapshots_without_zero_pages_still_restore() {
+    let mut src = Machine::new(minimal_machine_config()).unwrap();
+    src.write_physical(MARKER_ADDR, &[0x5A; 16]);
+    let (bytes, _) = save(&mut src, false);
+
+    let index = snapshot::inspect_snapshot(&mut Cursor::new(&bytes)).unwrap();
+    let ram_section = index
+        .sections
+        .iter()
+        .find(|s| s.id == snapshot::SectionId::RAM)
+        .unwrap();
+    assert_eq!(ram_section.version, 1);
+    assert!(!index.ram.unwrap().zero_pages);
+
+    let mut dst = Machine::new(minimal_machine_config()).unwrap();
+    dst.restore_snapshot_bytes(&bytes).unwrap();
+    assert_eq!(dst.read_physical_bytes(MARKER_ADDR, 16), vec![0x5A; 16]);
+}
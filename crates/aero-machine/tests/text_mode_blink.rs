@@ -0,0 +1,191 @@
+use aero_gpu_vga::{TEXT_BLINK_HALF_PERIOD_NS, TEXT_CURSOR_BLINK_HALF_PERIOD_NS};
+use aero_machine::{Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+const WHITE: u32 = 0xFFFF_FFFF;
+const BLACK: u32 = 0xFF00_0000;
+const LIGHT_GREY: u32 = 0xFFAA_AAAA;
+
+fn boot_halted(enable_aerogpu: bool) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: !enable_aerogpu,
+        enable_aerogpu,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    // cli; hlt
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    sector[..2].copy_from_slice(&[0xFA, 0xF4]);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    m.set_disk_image(sector.to_vec()).unwrap();
+    m.reset();
+    for _ in 0..100 {
+        match m.run_slice(50_000) {
+            RunExit::Halted { .. } => return m,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("machine did not halt within budget");
+}
+
+fn write_attribute_mode_control(m: &mut Machine, value: u8) {
+    // Reset the attribute flip-flop, then select index 0x10 (with PAS set) and write it.
+    m.io_read(0x3DA, 1);
+    m.io_write(0x3C0, 1, 0x30);
+    m.io_write(0x3C0, 1, u32::from(value));
+}
+
+/// Place the cursor at the top-left cell with the given CRTC-style start/end scanlines.
+///
+/// The standalone VGA device reads the CRTC registers; the AeroGPU legacy text path reads the
+/// BIOS Data Area, so program both.
+fn set_cursor(m: &mut Machine, start: u8, end: u8) {
+    for (index, value) in [(0x0A, start), (0x0B, end), (0x0E, 0), (0x0F, 0)] {
+        m.io_write(0x3D4, 1, index);
+        m.io_write(0x3D5, 1, u32::from(value));
+    }
+    m.write_physical_u16(0x450, 0); // page 0 cursor: col 0, row 0
+    m.write_physical_u8(0x460, end);
+    m.write_physical_u8(0x461, start);
+    m.write_physical_u8(0x462, 0); // active page 0
+    m.write_physical_u16(0x44E, 0); // page offset
+}
+
+fn pixel(m: &Machine, x: usize, y: usize) -> u32 {
+    let (w, _) = m.display_resolution();
+    m.display_framebuffer()[y * w as usize + x]
+}
+
+fn blink_attribute_toggles_one_guest_second_apart(enable_aerogpu: bool) {
+    let mut m = boot_halted(enable_aerogpu);
+    set_cursor(&mut m, 0x20, 0x00);
+    // Line graphics + blink enable.
+    write_attribute_mode_control(&mut m, 0x0C);
+    // Full block, blinking bright white on black.
+    m.write_physical_u16(0xB8000, 0x8FDB);
+
+    assert!(m.display_present(true));
+    let first = pixel(&m, 0, 0);
+
+    // One second of guest time is an odd number of blink half-periods, so the phase always flips.
+    m.tick_platform(1_000_000_000);
+    assert!(m.display_present(false));
+    let second = pixel(&m, 0, 0);
+    let mut phases = [first, second];
+    phases.sort_unstable();
+    assert_eq!(phases, [BLACK, WHITE]);
+
+    m.tick_platform(1_000_000_000);
+    assert!(m.display_present(false));
+    assert_eq!(pixel(&m, 0, 0), first);
+
+    // With blink disabled, attribute bit 7 selects background intensity and nothing blinks.
+    write_attribute_mode_control(&mut m, 0x04);
+    m.write_physical_u16(0xB8000, 0x8F20);
+    assert!(m.display_present(true));
+    let bright_bg = pixel(&m, 0, 0);
+    assert_ne!(bright_bg, BLACK);
+    assert_ne!(bright_bg, WHITE);
+    m.tick_platform(TEXT_BLINK_HALF_PERIOD_NS);
+    assert!(!m.display_present(false));
+}
+
+#[test]
+fn vga_text_blink_attribute_toggles_one_guest_second_apart() {
+    blink_attribute_toggles_one_guest_second_apart(false);
+}
+
+#[test]
+fn aerogpu_text_blink_attribute_toggles_one_guest_second_apart() {
+    blink_attribute_toggles_one_guest_second_apart(true);
+}
+
+fn cursor_blinks_on_crtc_scanlines(enable_aerogpu: bool) {
+    let mut m = boot_halted(enable_aerogpu);
+    m.write_physical_u16(0xB8000, 0x0720);
+    set_cursor(&mut m, 14, 15);
+
+    assert!(m.display_present(true));
+    let first = (pixel(&m, 0, 13), pixel(&m, 0, 14), pixel(&m, 8, 15));
+    m.tick_platform(TEXT_CURSOR_BLINK_HALF_PERIOD_NS);
+    assert!(m.display_present(false));
+    let second = (pixel(&m, 0, 13), pixel(&m, 0, 14), pixel(&m, 8, 15));
+
+    // Exactly one of two presents a half-period apart shows the cursor, and only on its scanlines.
+    let (on, off) = if first.1 == LIGHT_GREY {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    assert_eq!(on, (BLACK, LIGHT_GREY, LIGHT_GREY));
+    assert_eq!(off, (BLACK, BLACK, BLACK));
+
+    m.tick_platform(TEXT_CURSOR_BLINK_HALF_PERIOD_NS);
+    assert!(m.display_present(false));
+    assert_eq!(
+        (pixel(&m, 0, 13), pixel(&m, 0, 14), pixel(&m, 8, 15)),
+        first
+    );
+}
+
+#[test]
+fn vga_text_cursor_blinks_on_crtc_scanlines() {
+    cursor_blinks_on_crtc_scanlines(false);
+}
+
+#[test]
+fn aerogpu_text_cursor_blinks_on_crtc_scanlines() {
+    cursor_blinks_on_crtc_scanlines(true);
+}
+
+fn eight_dot_clocking_narrows_text_cells(enable_aerogpu: bool) {
+    let mut m = boot_halted(enable_aerogpu);
+    set_cursor(&mut m, 0x20, 0x00);
+    // '─' followed by '╗': only the line graphics range extends into the 9th column.
+    m.write_physical_u16(0xB8000, 0x0FC4);
+    m.write_physical_u16(0xB8002, 0x0FBB);
+
+    assert!(m.display_present(true));
+    assert_eq!(m.display_resolution(), (720, 400));
+    let line_y = (0..16).find(|&y| pixel(&m, 7, y) == WHITE).unwrap();
+    assert_eq!(pixel(&m, 8, line_y), WHITE);
+    assert!((0..16).all(|y| pixel(&m, 9 + 8, y) == BLACK));
+    let corner_9_dot: Vec<u32> = (0..16)
+        .flat_map(|y| (9..17).map(move |x| (x, y)))
+        .map(|(x, y)| pixel(&m, x, y))
+        .collect();
+
+    // Sequencer Clocking Mode bit 0 selects 8-dot characters.
+    m.io_write(0x3C4, 1, 0x01);
+    m.io_write(0x3C5, 1, 0x01);
+    assert!(m.display_present(true));
+    assert_eq!(m.display_resolution(), (640, 400));
+    assert_eq!(pixel(&m, 7, line_y), WHITE);
+    // The second cell now starts at x=8 and shows the same 8 glyph columns.
+    let corner_8_dot: Vec<u32> = (0..16)
+        .flat_map(|y| (8..16).map(move |x| (x, y)))
+        .map(|(x, y)| pixel(&m, x, y))
+        .collect();
+    assert_eq!(corner_8_dot, corner_9_dot);
+}
+
+#[test]
+fn vga_text_eight_dot_clocking_narrows_text_cells() {
+    eight_dot_clocking_narrows_text_cells(false);
+}
+
+#[test]
+fn aerogpu_text_eight_dot_clocking_narrows_text_cells() {
+    eight_dot_clocking_narrows_text_cells(true);
+}
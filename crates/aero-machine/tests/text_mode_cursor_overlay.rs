@@ -86,7 +86,7 @@ fn text_mode_cursor_overlay_renders_from_crtc_regs() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
     let (width, _height) = m.display_resolution();
     let fb = m.display_framebuffer();
     let px0 = fb[0];
@@ -129,7 +129,7 @@ fn text_mode_cursor_overlay_respects_disable_bit() {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
     let pixel0 = m.display_framebuffer()[0];
 
     assert_eq!(pixel0, 0xFFAA_0000);
@@ -188,7 +188,7 @@ fn text_mode_cursor_overlay_respects_crtc_start_address() {
     m.write_physical_u8(base, b' ');
     m.write_physical_u8(base + 1, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
     let (width, _height) = m.display_resolution();
     let fb = m.display_framebuffer();
     let px0 = fb[0];
@@ -0,0 +1,154 @@
+use aero_machine::{Machine, MachineConfig, TextCell};
+use firmware::bda::{
+    BDA_ACTIVE_PAGE_ADDR, BDA_CURSOR_POS_PAGE0_ADDR, BDA_SCREEN_COLS_ADDR,
+    BDA_TEXT_ROWS_MINUS_ONE_ADDR, BDA_VIDEO_PAGE_OFFSET_ADDR,
+};
+use pretty_assertions::assert_eq;
+
+fn new_machine(enable_vga: bool) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu: !enable_vga,
+        enable_vga,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn write_text(m: &mut Machine, addr: u64, text: &[u8], attr: u8) {
+    for (i, &ch) in text.iter().enumerate() {
+        m.write_physical_u8(addr + i as u64 * 2, ch);
+        m.write_physical_u8(addr + i as u64 * 2 + 1, attr);
+    }
+}
+
+fn set_bda_geometry(m: &mut Machine, cols: u16, rows: u8) {
+    m.write_physical_u16(BDA_SCREEN_COLS_ADDR, cols);
+    m.write_physical_u8(BDA_TEXT_ROWS_MINUS_ONE_ADDR, rows - 1);
+}
+
+#[test]
+fn aerogpu_text_screen_reads_the_active_page_and_its_cursor() {
+    let mut m = new_machine(false);
+    m.write_physical(0xB8000, &vec![0u8; 0x8000]);
+    set_bda_geometry(&mut m, 80, 25);
+
+    write_text(&mut m, 0xB8000, b"page zero", 0x07);
+    // Page 1 at the BIOS page size of 0x1000 bytes: CP437 box drawing and a blinking attribute.
+    write_text(
+        &mut m,
+        0xB9000 + 80 * 2,
+        b"\xC9\xCD\xBB Press any key",
+        0x9E,
+    );
+    m.write_physical_u8(BDA_ACTIVE_PAGE_ADDR, 1);
+    m.write_physical_u16(BDA_VIDEO_PAGE_OFFSET_ADDR, 0x1000);
+    // Page 0 cursor at (0, 9); page 1 cursor at row 1, column 18 (col in the low byte).
+    m.write_physical_u16(BDA_CURSOR_POS_PAGE0_ADDR, 9);
+    m.write_physical_u16(BDA_CURSOR_POS_PAGE0_ADDR + 2, (1 << 8) | 18);
+
+    let screen = m.read_text_screen().unwrap();
+    assert_eq!((screen.cols, screen.rows), (80, 25));
+    assert_eq!(screen.cells.len(), 80 * 25);
+    assert_eq!(screen.row_text(0), "");
+    assert_eq!(screen.row_text(1), "╔═╗ Press any key");
+    assert!(screen.text().contains("Press any key"));
+    assert_eq!(screen.cursor, (1, 18));
+    // Blink is disabled by default, so attribute bit 7 is a bright background.
+    assert_eq!(
+        screen.cell(1, 0),
+        Some(&TextCell {
+            ch: '╔',
+            fg: 0x0E,
+            bg: 0x09,
+            blink: false,
+        })
+    );
+
+    // Enable blink (Attribute Controller Mode Control bit 3).
+    m.io_read(0x3DA, 1);
+    m.io_write(0x3C0, 1, 0x30);
+    m.io_write(0x3C0, 1, 0x0C);
+    let screen = m.read_text_screen().unwrap();
+    assert_eq!(
+        screen.cell(1, 4),
+        Some(&TextCell {
+            ch: 'P',
+            fg: 0x0E,
+            bg: 0x01,
+            blink: true,
+        })
+    );
+
+    // Back to page 0.
+    m.write_physical_u8(BDA_ACTIVE_PAGE_ADDR, 0);
+    m.write_physical_u16(BDA_VIDEO_PAGE_OFFSET_ADDR, 0);
+    let screen = m.read_text_screen().unwrap();
+    assert_eq!(screen.row_text(0), "page zero");
+    assert_eq!(screen.cursor, (0, 9));
+}
+
+#[test]
+fn aerogpu_text_screen_follows_bda_geometry_for_80x50() {
+    let mut m = new_machine(false);
+    m.write_physical(0xB8000, &vec![0u8; 0x8000]);
+    set_bda_geometry(&mut m, 80, 50);
+    m.write_physical_u8(BDA_ACTIVE_PAGE_ADDR, 0);
+    m.write_physical_u16(BDA_VIDEO_PAGE_OFFSET_ADDR, 0);
+
+    write_text(&mut m, 0xB8000 + 49 * 80 * 2 + 70 * 2, b"last row", 0x1F);
+    m.write_physical_u16(BDA_CURSOR_POS_PAGE0_ADDR, (49 << 8) | 78);
+
+    let screen = m.read_text_screen().unwrap();
+    assert_eq!((screen.cols, screen.rows), (80, 50));
+    assert_eq!(screen.cells.len(), 80 * 50);
+    assert_eq!(screen.row_text(49), format!("{}last row", " ".repeat(70)));
+    assert_eq!(screen.cursor, (49, 78));
+    assert_eq!(screen.cell(50, 0), None);
+
+    // A VBE (Bochs DISPI) graphics mode has no text screen.
+    m.io_write(0x01CE, 2, 0x0004);
+    m.io_write(0x01CF, 2, 0x0001);
+    assert_eq!(m.read_text_screen(), None);
+}
+
+#[test]
+fn vga_text_screen_follows_crtc_start_address_and_cursor() {
+    let mut m = new_machine(true);
+    m.write_physical(0xB8000, &vec![0u8; 0x8000]);
+    write_text(&mut m, 0xB9000, b"\x01 page one", 0x2F);
+
+    // CRTC start address = page 1 (0x800 cells); cursor at row 3, column 7 of that page.
+    let cursor = 0x800 + 3 * 80 + 7;
+    for (index, value) in [
+        (0x0C, 0x08),
+        (0x0D, 0x00),
+        (0x0E, cursor >> 8),
+        (0x0F, cursor & 0xFF),
+    ] {
+        m.io_write(0x3D4, 1, index);
+        m.io_write(0x3D5, 1, value);
+    }
+
+    let screen = m.read_text_screen().unwrap();
+    assert_eq!((screen.cols, screen.rows), (80, 25));
+    assert_eq!(screen.row_text(0), "☺ page one");
+    assert_eq!(screen.cursor, (3, 7));
+    assert_eq!(
+        screen.cell(0, 2).map(|cell| (cell.fg, cell.bg)),
+        Some((0x0F, 0x02))
+    );
+
+    // Attribute Controller Mode Control bit 0 switches to graphics.
+    m.io_read(0x3DA, 1);
+    m.io_write(0x3C0, 1, 0x30);
+    m.io_write(0x3C0, 1, 0x41);
+    assert_eq!(m.read_text_screen(), None);
+}
@@ -0,0 +1,178 @@
+use aero_devices::clock::Clock as _;
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_devices::pit8254::{PIT_CH0, PIT_CMD};
+use aero_machine::{Machine, MachineConfig, TimerCatchupPolicy};
+use pretty_assertions::assert_eq;
+
+const LAPIC_TIMER_VECTOR: u8 = 0x40;
+const PAUSE_NS: u64 = 60_000_000_000;
+// ~1ms PIT period.
+const PIT_DIVISOR: u32 = 1193;
+
+fn new_machine(policy: Option<TimerCatchupPolicy>) -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_timer_catchup_policy(policy);
+    m
+}
+
+fn now_ns(m: &Machine) -> u64 {
+    m.platform_clock().unwrap().now_ns()
+}
+
+fn read_rtc_reg_c(m: &mut Machine) -> u8 {
+    m.io_write(0x70, 1, 0x0C);
+    m.io_read(0x71, 1) as u8
+}
+
+/// Arm every periodic timer source: PIT channel 0 (~1kHz), RTC (1024Hz), HPET timer 0 (1kHz) and
+/// the LAPIC timer.
+fn arm_periodic_timers(m: &mut Machine) {
+    // PIT channel 0, lobyte/hibyte, mode 2 (rate generator).
+    m.io_write(PIT_CMD, 1, 0x34);
+    m.io_write(PIT_CH0, 1, PIT_DIVISOR & 0xFF);
+    m.io_write(PIT_CH0, 1, PIT_DIVISOR >> 8);
+
+    // RTC register A: 32.768kHz divider, rate 6 (1024Hz). Register B: 24h mode + PIE.
+    read_rtc_reg_c(m);
+    m.io_write(0x70, 1, 0x0A);
+    m.io_write(0x71, 1, 0x26);
+    m.io_write(0x70, 1, 0x0B);
+    m.io_write(0x71, 1, 0x42);
+
+    // Fast A20 gate: the HPET base aliases the IOAPIC while A20 is masked.
+    m.io_write(0x92, 1, 0x02);
+    // Timer0: level-triggered, interrupt enabled, periodic, with a 1ms (10_000 tick) period.
+    m.write_physical_u64(
+        HPET_MMIO_BASE + 0x100,
+        (1 << 1) | (1 << 2) | (1 << 3) | (1 << 6),
+    );
+    m.write_physical_u64(HPET_MMIO_BASE + 0x108, 10_000);
+    m.write_physical_u64(HPET_MMIO_BASE + 0x010, 1);
+
+    let interrupts = m.platform_interrupts().unwrap();
+    let ints = interrupts.borrow();
+    // Software-enable the LAPIC, divide by 1, periodic timer on `LAPIC_TIMER_VECTOR`.
+    ints.lapic_mmio_write(0x0F0, &0x1FFu32.to_le_bytes());
+    ints.lapic_mmio_write(0x3E0, &0xBu32.to_le_bytes());
+    ints.lapic_mmio_write(
+        0x320,
+        &((1u32 << 17) | u32::from(LAPIC_TIMER_VECTOR)).to_le_bytes(),
+    );
+    ints.lapic_mmio_write(0x380, &5_000u32.to_le_bytes());
+}
+
+/// Latch and read the PIT channel 0 count.
+fn read_pit_count(m: &mut Machine) -> u16 {
+    m.io_write(PIT_CMD, 1, 0x00);
+    let lo = m.io_read(PIT_CH0, 1) as u16;
+    let hi = m.io_read(PIT_CH0, 1) as u16;
+    lo | (hi << 8)
+}
+
+fn read_lapic_current_count(m: &Machine) -> u32 {
+    let mut buf = [0u8; 4];
+    m.platform_interrupts()
+        .unwrap()
+        .borrow()
+        .lapic_mmio_read(0x390, &mut buf);
+    u32::from_le_bytes(buf)
+}
+
+/// Observable timer state after a pause: `(pit_pulses, pit_count, hpet_counter, lapic_count)`.
+fn pause(m: &mut Machine) -> (u64, u16, u64, u32) {
+    arm_periodic_timers(m);
+    m.pit().unwrap().borrow_mut().take_irq0_pulses();
+    let start = now_ns(m);
+    m.tick_platform(PAUSE_NS);
+    assert_eq!(now_ns(m) - start, PAUSE_NS);
+
+    let pulses = m.pit().unwrap().borrow_mut().take_irq0_pulses();
+    (
+        pulses,
+        read_pit_count(m),
+        m.read_physical_u64(HPET_MMIO_BASE + 0x0F0),
+        read_lapic_current_count(m),
+    )
+}
+
+#[test]
+fn long_pause_delivers_bounded_interrupts_but_advances_counters_fully() {
+    let policy = TimerCatchupPolicy::default();
+    let mut relaxed = new_machine(Some(policy));
+    let mut strict = new_machine(None);
+
+    let (relaxed_pulses, relaxed_pit, relaxed_hpet, relaxed_lapic) = pause(&mut relaxed);
+    let (strict_pulses, strict_pit, strict_hpet, strict_lapic) = pause(&mut strict);
+
+    // Strict mode delivers every PIT period of the 60s gap.
+    let periods = PAUSE_NS * 1_193_182 / 1_000_000_000 / u64::from(PIT_DIVISOR);
+    assert!(
+        strict_pulses.abs_diff(periods) <= 1,
+        "{strict_pulses} vs {periods}"
+    );
+
+    // Under the policy the guest sees a bounded burst, and the rest is accounted as coalesced.
+    assert_eq!(relaxed_pulses, policy.max_burst);
+    assert_eq!(
+        relaxed.pit().unwrap().borrow().irq0_coalesced_pulses(),
+        strict_pulses - policy.max_burst
+    );
+    assert_eq!(strict.pit().unwrap().borrow().irq0_coalesced_pulses(), 0);
+
+    // Counters advance by the full elapsed time regardless of the policy.
+    assert_eq!(relaxed_pit, strict_pit);
+    assert_eq!(relaxed_hpet, strict_hpet);
+    assert!(relaxed_hpet >= PAUSE_NS / 100, "{relaxed_hpet}");
+    assert_eq!(relaxed_lapic, strict_lapic);
+
+    // Latched sources hold one pending interrupt each in both modes.
+    for m in [&mut relaxed, &mut strict] {
+        assert_eq!(read_rtc_reg_c(m) & 0x40, 0x40);
+        assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 0x020) & 1, 1);
+        assert!(m
+            .platform_interrupts()
+            .unwrap()
+            .borrow()
+            .lapic(0)
+            .is_pending(LAPIC_TIMER_VECTOR));
+    }
+}
+
+#[test]
+fn short_steps_are_not_coalesced() {
+    let mut m = new_machine(Some(TimerCatchupPolicy::default()));
+    arm_periodic_timers(&mut m);
+    m.pit().unwrap().borrow_mut().take_irq0_pulses();
+
+    // 10ms steps cover ~10 PIT periods each, well below the threshold.
+    for _ in 0..100 {
+        m.tick_platform(10_000_000);
+    }
+    let pulses = m.pit().unwrap().borrow_mut().take_irq0_pulses();
+    assert!(pulses.abs_diff(1000) <= 1, "{pulses}");
+    assert_eq!(m.pit().unwrap().borrow().irq0_coalesced_pulses(), 0);
+}
+
+#[test]
+fn catchup_policy_is_host_config_and_survives_reset() {
+    let mut m = new_machine(Some(TimerCatchupPolicy::default()));
+    assert_eq!(
+        m.timer_catchup_policy(),
+        Some(TimerCatchupPolicy::default())
+    );
+
+    m.set_timer_catchup_policy(None);
+    m.reset();
+    assert_eq!(m.timer_catchup_policy(), None);
+    let (pulses, ..) = pause(&mut m);
+    assert!(pulses > 1000, "{pulses}");
+}
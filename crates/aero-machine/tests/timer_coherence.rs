@@ -0,0 +1,165 @@
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_machine::{
+    Machine, MachineConfig, TimerCoherenceConfig, TimerCoherenceReport, TimerSource,
+};
+use pretty_assertions::assert_eq;
+
+const RTC_REG_B_PIE: u8 = 1 << 6;
+
+fn new_machine(cpu_count: u8) -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        cpu_count,
+        enable_pc_platform: true,
+        enable_acpi: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Start the sources a guest has to enable itself: the HPET main counter and the RTC periodic
+/// interrupt (at the default 1024Hz rate in register A).
+fn start_guest_timers(m: &mut Machine) {
+    m.write_physical_u64(HPET_MMIO_BASE + 0x10, 1);
+    m.io_write(0x70, 1, 0x0B);
+    let reg_b = m.io_read(0x71, 1) as u8;
+    m.io_write(0x70, 1, 0x0B);
+    m.io_write(0x71, 1, u32::from(reg_b | RTC_REG_B_PIE));
+}
+
+fn assert_coherent(report: &TimerCoherenceReport) {
+    let outliers: Vec<_> = report.outliers().collect();
+    assert!(outliers.is_empty(), "timer sources disagree: {outliers:#?}");
+}
+
+fn sources(report: &TimerCoherenceReport) -> Vec<TimerSource> {
+    report.samples.iter().map(|sample| sample.source).collect()
+}
+
+#[test]
+fn all_timekeeping_sources_agree_over_one_second() {
+    let mut m = new_machine(1);
+    start_guest_timers(&mut m);
+
+    let report = m.verify_timer_coherence(1_000_000_000);
+
+    assert_eq!(report.elapsed_ns, 1_000_000_000);
+    assert_eq!(
+        sources(&report),
+        [
+            TimerSource::Pit,
+            TimerSource::PmTimer,
+            TimerSource::Hpet,
+            TimerSource::Tsc(0),
+            TimerSource::RtcPeriodic,
+        ]
+    );
+    assert!(report.not_running.is_empty());
+    assert_coherent(&report);
+
+    // Over exactly one second every counter lands within a tick of its nominal frequency.
+    for sample in &report.samples {
+        let expected = sample.nominal_hz;
+        assert!(
+            (sample.ticks as f64 - expected).abs() <= 1.0,
+            "{:?}: {} ticks, expected {expected}",
+            sample.source,
+            sample.ticks
+        );
+    }
+}
+
+#[test]
+fn sources_agree_across_tick_granularities() {
+    for step_ns in [1_000, 7_919, 250_000, 1_000_000, 16_666_667] {
+        let mut m = new_machine(1);
+        start_guest_timers(&mut m);
+
+        // Keep the number of platform ticks bounded for the finest granularities.
+        let duration_ns = (step_ns * 50_000).min(2_000_000_000);
+        let report = m.verify_timer_coherence_with(
+            duration_ns,
+            &TimerCoherenceConfig {
+                step_ns,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(report.step_ns, step_ns, "step {step_ns}ns");
+        assert_eq!(report.samples.len(), 5, "step {step_ns}ns");
+        assert_coherent(&report);
+    }
+}
+
+#[test]
+fn pm_timer_wraparound_is_unfolded() {
+    let mut m = new_machine(1);
+
+    // The 24-bit PM timer wraps every ~4.69s.
+    let report = m.verify_timer_coherence_with(
+        10_000_000_000,
+        &TimerCoherenceConfig {
+            step_ns: 100_000_000,
+            ..Default::default()
+        },
+    );
+
+    let pm = report.sample(TimerSource::PmTimer).unwrap();
+    assert!(pm.ticks > 0x0100_0000 * 2, "ticks {}", pm.ticks);
+    assert_coherent(&report);
+}
+
+#[test]
+fn every_vcpu_tsc_is_checked() {
+    let mut m = new_machine(4);
+    start_guest_timers(&mut m);
+
+    let report = m.verify_timer_coherence(100_000_000);
+
+    for cpu in 0..4 {
+        let tsc = report
+            .sample(TimerSource::Tsc(cpu))
+            .unwrap_or_else(|| panic!("missing TSC sample for vCPU {cpu}"));
+        assert_eq!(tsc.deviation_ppm, 0.0, "vCPU {cpu}");
+    }
+    assert_coherent(&report);
+}
+
+#[test]
+fn sources_the_guest_has_not_started_are_reported_as_not_running() {
+    let mut m = new_machine(1);
+
+    let report = m.verify_timer_coherence(10_000_000);
+
+    assert_eq!(
+        report.not_running,
+        [TimerSource::Hpet, TimerSource::RtcPeriodic]
+    );
+    assert_eq!(
+        sources(&report),
+        [TimerSource::Pit, TimerSource::PmTimer, TimerSource::Tsc(0)]
+    );
+    assert_coherent(&report);
+}
+
+#[test]
+fn zero_tolerance_still_allows_one_tick_of_counting_error() {
+    let mut m = new_machine(1);
+    start_guest_timers(&mut m);
+
+    let report = m.verify_timer_coherence_with(
+        3_333_333,
+        &TimerCoherenceConfig {
+            tolerance_ppm: 0,
+            ..Default::default()
+        },
+    );
+
+    let rtc = report.sample(TimerSource::RtcPeriodic).unwrap();
+    assert!(rtc.resolution_ppm > 100_000.0);
+    assert_coherent(&report);
+}
@@ -0,0 +1,152 @@
+use aero_devices::clock::Clock as _;
+use aero_devices::hpet::HPET_MMIO_BASE;
+use aero_devices::pit8254::{PIT_CH0, PIT_CMD};
+use aero_machine::{DirectBootMode, DirectBootSpec, Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+const LAPIC_TIMER_VECTOR: u8 = 0x40;
+
+fn new_machine() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    // BIOS POST leaves PIT channel 0 running at 18.2Hz; park it in one-shot mode (no periodic
+    // pulses) so each test only observes the source it programs.
+    m.io_write(PIT_CMD, 1, 0x30);
+    m.pit().unwrap().borrow_mut().take_irq0_pulses();
+    m
+}
+
+fn now_ns(m: &Machine) -> u64 {
+    m.platform_clock().unwrap().now_ns()
+}
+
+/// Advance platform time to one nanosecond before `deadline_ns`.
+fn tick_until_just_before(m: &mut Machine, deadline_ns: u64) {
+    let now = now_ns(m);
+    assert!(
+        deadline_ns > now,
+        "deadline {deadline_ns} is not after now {now}"
+    );
+    m.tick_platform(deadline_ns - now - 1);
+}
+
+fn read_rtc_reg_c(m: &mut Machine) -> u8 {
+    m.io_write(0x70, 1, 0x0C);
+    m.io_read(0x71, 1) as u8
+}
+
+#[test]
+fn no_deadline_when_no_timer_is_armed() {
+    let m = new_machine();
+    assert_eq!(m.next_timer_deadline_ns(), None);
+}
+
+#[test]
+fn pit_deadline_matches_irq0_pulse() {
+    let mut m = new_machine();
+
+    // Channel 0, lobyte/hibyte, mode 2 (rate generator), divisor 1193 (~1ms).
+    m.io_write(PIT_CMD, 1, 0x34);
+    m.io_write(PIT_CH0, 1, 1193 & 0xFF);
+    m.io_write(PIT_CH0, 1, 1193 >> 8);
+
+    let deadline = m.next_timer_deadline_ns().expect("PIT deadline");
+    tick_until_just_before(&mut m, deadline);
+    assert_eq!(m.pit().unwrap().borrow_mut().take_irq0_pulses(), 0);
+
+    m.tick_platform(1);
+    assert_eq!(m.pit().unwrap().borrow_mut().take_irq0_pulses(), 1);
+    assert!(m.next_timer_deadline_ns().unwrap() > now_ns(&m));
+}
+
+#[test]
+fn rtc_periodic_deadline_matches_pf_flag() {
+    let mut m = new_machine();
+    // Clear any latched flags before arming the periodic interrupt.
+    read_rtc_reg_c(&mut m);
+
+    // Register A: 32.768kHz divider, rate 6 (1024Hz). Register B: 24h mode + PIE.
+    m.io_write(0x70, 1, 0x0A);
+    m.io_write(0x71, 1, 0x26);
+    m.io_write(0x70, 1, 0x0B);
+    m.io_write(0x71, 1, 0x42);
+
+    let deadline = m.next_timer_deadline_ns().expect("RTC deadline");
+    tick_until_just_before(&mut m, deadline);
+    assert_eq!(read_rtc_reg_c(&mut m) & 0x40, 0);
+
+    m.tick_platform(1);
+    assert_eq!(read_rtc_reg_c(&mut m) & 0x40, 0x40);
+}
+
+#[test]
+fn hpet_deadline_matches_comparator_match() {
+    let mut m = new_machine();
+    // Fast A20 gate: the HPET base aliases the IOAPIC while A20 is masked.
+    m.io_write(0x92, 1, 0x02);
+
+    // Timer0: level-triggered so the general interrupt status latches the match.
+    m.write_physical_u64(HPET_MMIO_BASE + 0x100, (1 << 1) | (1 << 2));
+    // Comparator: 10_000 ticks at 10MHz is 1ms.
+    m.write_physical_u64(HPET_MMIO_BASE + 0x108, 10_000);
+    m.write_physical_u64(HPET_MMIO_BASE + 0x010, 1);
+
+    let deadline = m.next_timer_deadline_ns().expect("HPET deadline");
+    assert_eq!(deadline - now_ns(&m), 1_000_000);
+    tick_until_just_before(&mut m, deadline);
+    assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 0x020) & 1, 0);
+
+    m.tick_platform(1);
+    assert_eq!(m.read_physical_u64(HPET_MMIO_BASE + 0x020) & 1, 1);
+}
+
+#[test]
+fn lapic_deadline_matches_timer_expiry() {
+    let mut m = new_machine();
+    let interrupts = m.platform_interrupts().unwrap();
+    {
+        let ints = interrupts.borrow();
+        // Software-enable the LAPIC, divide by 1, one-shot timer on `LAPIC_TIMER_VECTOR`.
+        ints.lapic_mmio_write(0x0F0, &0x1FFu32.to_le_bytes());
+        ints.lapic_mmio_write(0x3E0, &0xBu32.to_le_bytes());
+        ints.lapic_mmio_write(0x320, &u32::from(LAPIC_TIMER_VECTOR).to_le_bytes());
+        ints.lapic_mmio_write(0x380, &5_000u32.to_le_bytes());
+    }
+
+    let deadline = m.next_timer_deadline_ns().expect("LAPIC deadline");
+    tick_until_just_before(&mut m, deadline);
+    assert!(!interrupts.borrow().lapic(0).is_pending(LAPIC_TIMER_VECTOR));
+
+    m.tick_platform(1);
+    assert!(interrupts.borrow().lapic(0).is_pending(LAPIC_TIMER_VECTOR));
+}
+
+#[test]
+fn run_slice_returns_immediately_when_all_vcpus_are_idle() {
+    let mut m = new_machine();
+    // cli; hlt
+    m.boot_direct(DirectBootSpec {
+        mode: DirectBootMode::Protected32,
+        entry: 0x10_0000,
+        blobs: vec![(0x10_0000, vec![0xFA, 0xF4])],
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(!m.all_vcpus_halted());
+
+    assert!(matches!(m.run_slice(1_000), RunExit::Halted { .. }));
+    assert!(m.all_vcpus_halted());
+    assert!(matches!(
+        m.run_slice(1_000),
+        RunExit::Halted { executed: 0 }
+    ));
+}
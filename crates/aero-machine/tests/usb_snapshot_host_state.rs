@@ -223,8 +223,8 @@ fn snapshot_restore_clears_muxed_webusb_host_state() {
     .unwrap();
 
     let webusb = UsbWebUsbPassthroughDevice::new();
-    vm.usb_attach_root(WEBUSB_ROOT_PORT, Box::new(webusb.clone()))
-        .expect("attach WebUSB device behind UHCI");
+    vm.usb_attach_webusb_passthrough(webusb.clone())
+        .expect("attach WebUSB device to its physical port");
 
     // When both UHCI and EHCI are enabled, the first two root ports are backed by a shared USB2
     // mux, so the same physical device should be visible from both controllers.
@@ -236,7 +236,7 @@ fn snapshot_restore_clears_muxed_webusb_host_state() {
                 .hub()
                 .port_device(usize::from(WEBUSB_ROOT_PORT))
                 .is_some(),
-            "expected EHCI to observe the UHCI-attached device via the shared USB2 mux"
+            "expected EHCI to observe the physically attached device via the shared USB2 mux"
         );
     }
 
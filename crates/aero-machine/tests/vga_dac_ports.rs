@@ -111,7 +111,7 @@ fn run_dac_port_roundtrip(m: &mut Machine, expect_display_palette: bool) {
     m.write_physical_u8(0xB8000, b' ');
     m.write_physical_u8(0xB8001, 0x10); // bg=1, fg=0
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (720, 400));
     // RGBA8888 little-endian u32: [R, G, B, A].
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
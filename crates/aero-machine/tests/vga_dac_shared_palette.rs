@@ -0,0 +1,156 @@
+use aero_machine::{Machine, MachineConfig, RunExit};
+use pretty_assertions::assert_eq;
+
+fn build_int10_set_mode13h_boot_sector() -> [u8; aero_storage::SECTOR_SIZE] {
+    let mut sector = [0u8; aero_storage::SECTOR_SIZE];
+    let mut i = 0usize;
+
+    // mov ax, 0x0013 (set video mode 13h)
+    sector[i..i + 3].copy_from_slice(&[0xB8, 0x13, 0x00]);
+    i += 3;
+    // int 0x10
+    sector[i..i + 2].copy_from_slice(&[0xCD, 0x10]);
+    i += 2;
+
+    // hlt
+    sector[i] = 0xF4;
+
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(50_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => continue,
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+fn config(enable_aerogpu: bool) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_aerogpu,
+        enable_vga: !enable_aerogpu,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    }
+}
+
+fn boot_mode13h(enable_aerogpu: bool) -> Machine {
+    let mut m = Machine::new(config(enable_aerogpu)).unwrap();
+    m.set_disk_image(build_int10_set_mode13h_boot_sector().to_vec())
+        .unwrap();
+    m.reset();
+    run_until_halt(&mut m);
+
+    // One pixel per palette index on the first scanlines.
+    for idx in 0..256u64 {
+        m.write_physical_u8(0xA0000 + idx, idx as u8);
+    }
+    m
+}
+
+fn present(m: &mut Machine) -> Vec<u32> {
+    m.display_present(true);
+    assert_eq!(m.display_resolution(), (320, 200));
+    m.display_framebuffer().to_vec()
+}
+
+fn program_palette(m: &mut Machine) {
+    m.io_write(0x3C8, 1, 0x10);
+    // Entry 0x10: classic 6-bit components.
+    for c in [0x3F, 0x15, 0x01] {
+        m.io_write(0x3C9, 1, c);
+    }
+    // Entry 0x11: 8-bit components (auto-detected and downscaled).
+    for c in [0x80, 0x41, 0xFF] {
+        m.io_write(0x3C9, 1, c);
+    }
+    // Entry 0x12: a partial triplet; only R/G are latched.
+    for c in [0x22, 0x33] {
+        m.io_write(0x3C9, 1, c);
+    }
+
+    // Restart at entry 0xFF and wrap around to entry 0.
+    m.io_write(0x3C8, 1, 0xFF);
+    for c in [0x01, 0x02, 0x03, 0x3E, 0x3D, 0x3C] {
+        m.io_write(0x3C9, 1, c);
+    }
+
+    m.io_write(0x3C6, 1, 0xF7);
+}
+
+fn read_dac(m: &mut Machine) -> Vec<u8> {
+    let mut out = vec![m.io_read(0x3C6, 1) as u8, m.io_read(0x3C8, 1) as u8];
+    m.io_write(0x3C7, 1, 0);
+    for _ in 0..256 * 3 {
+        out.push(m.io_read(0x3C9, 1) as u8);
+    }
+    out
+}
+
+#[test]
+fn vga_and_aerogpu_render_default_palette_identically() {
+    let mut vga = boot_mode13h(false);
+    let mut aerogpu = boot_mode13h(true);
+
+    let vga_fb = present(&mut vga);
+    assert_eq!(vga_fb, present(&mut aerogpu));
+    // EGA color 1 (blue) and the first color cube entry (black).
+    assert_eq!(vga_fb[1], 0xFFAA_0000);
+    assert_eq!(vga_fb[16], 0xFF00_0000);
+}
+
+#[test]
+fn vga_and_aerogpu_render_guest_programmed_palette_identically() {
+    let mut vga = boot_mode13h(false);
+    let mut aerogpu = boot_mode13h(true);
+    program_palette(&mut vga);
+    program_palette(&mut aerogpu);
+
+    assert_eq!(read_dac(&mut vga), read_dac(&mut aerogpu));
+
+    let vga_fb = present(&mut vga);
+    assert_eq!(vga_fb, present(&mut aerogpu));
+    // Entry 0x10 is unaffected by the PEL mask; pixel 0x19 is masked down to entry 0x11.
+    assert_eq!(vga_fb[0x10], 0xFF04_55FF);
+    assert_eq!(vga_fb[0x19], 0xFFFF_4182);
+}
+
+#[test]
+fn dac_state_survives_snapshot_on_both_device_models() {
+    for enable_aerogpu in [false, true] {
+        let mut m = boot_mode13h(enable_aerogpu);
+        program_palette(&mut m);
+        // Leave a half-written triplet; the latched component must survive restore.
+        m.io_write(0x3C8, 1, 0x40);
+        m.io_write(0x3C9, 1, 0x30);
+        let fb = present(&mut m);
+        let snap = m.take_snapshot_full().unwrap();
+
+        let mut restored = Machine::new(config(enable_aerogpu)).unwrap();
+        restored
+            .set_disk_image(build_int10_set_mode13h_boot_sector().to_vec())
+            .unwrap();
+        restored.reset();
+        restored.restore_snapshot_bytes(&snap).unwrap();
+        assert_eq!(present(&mut restored), fb);
+
+        for m in [&mut m, &mut restored] {
+            m.io_write(0x3C9, 1, 0x20);
+            m.io_write(0x3C9, 1, 0x10);
+        }
+        assert_eq!(read_dac(&mut restored), read_dac(&mut m));
+    }
+}
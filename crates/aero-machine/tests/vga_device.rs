@@ -57,7 +57,7 @@ fn vga_vbe_linear_framebuffer_scanout_matches_expected_pixels() {
     write_pixel_bgrx(&mut m, 64, 1, 0, 0x00, 0xFF, 0x00); // green
     write_pixel_bgrx(&mut m, 64, 0, 1, 0xFF, 0x00, 0x00); // blue
 
-    m.display_present();
+    m.display_present(true);
 
     assert_eq!(m.display_resolution(), (64, 64));
     let fb = m.display_framebuffer();
@@ -85,7 +85,7 @@ fn vga_snapshot_roundtrip_preserves_scanout() {
     write_pixel_bgrx(&mut m, 64, 0, 0, 0x00, 0x00, 0xFF);
     write_pixel_bgrx(&mut m, 64, 1, 0, 0x00, 0xFF, 0x00);
     write_pixel_bgrx(&mut m, 64, 0, 1, 0xFF, 0x00, 0x00);
-    m.display_present();
+    m.display_present(true);
     let expected_res = m.display_resolution();
     let expected_fb: Vec<u32> = m.display_framebuffer().to_vec();
 
@@ -93,7 +93,7 @@ fn vga_snapshot_roundtrip_preserves_scanout() {
 
     let mut restored = Machine::new(cfg).unwrap();
     restored.restore_snapshot_bytes(&snap).unwrap();
-    restored.display_present();
+    restored.display_present(true);
     assert_eq!(restored.vbe_lfb_base(), u64::from(NON_DEFAULT_LFB_BASE));
 
     assert_eq!(restored.display_resolution(), expected_res);
@@ -101,7 +101,7 @@ fn vga_input_status1_vertical_retrace_bit_tracks_vblank_cadence() {
         MachineConfig {
             enable_pc_platform: false,
             enable_vga: true,
-            ..base_cfg
+            ..base_cfg.clone()
         },
     );
 
@@ -111,7 +111,7 @@ fn vga_input_status1_vertical_retrace_bit_tracks_vblank_cadence() {
         MachineConfig {
             enable_pc_platform: true,
             enable_vga: true,
-            ..base_cfg
+            ..base_cfg.clone()
         },
     );
 
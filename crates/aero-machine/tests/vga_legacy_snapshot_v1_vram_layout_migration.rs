@@ -122,7 +122,7 @@ fn machine_restore_migrates_legacy_vga_snapshot_v1_vram_layout() {
     let mut vm2 = Machine::new(cfg).unwrap();
     vm2.restore_snapshot_bytes(&snap_bytes).unwrap();
 
-    vm2.display_present();
+    vm2.display_present(true);
     assert_eq!(vm2.display_resolution(), (64, 64));
     assert_eq!(vm2.display_framebuffer()[0], 0xFF00_00FF);
 }
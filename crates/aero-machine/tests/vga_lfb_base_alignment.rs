@@ -49,7 +49,7 @@ fn vga_lfb_base_is_aligned_down_to_vga_pci_bar_size() {
     // Write a red pixel at (0,0) in packed 32bpp BGRX via *machine memory*.
     m.write_physical_u32(u64::from(expected_aligned_base), 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -103,7 +103,7 @@ fn vga_lfb_base_alignment_respects_rounded_vram_size() {
 
     m.write_physical_u32(u64::from(expected_aligned_base), 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -168,7 +168,7 @@ fn vga_lfb_base_alignment_applies_to_vram_bar_base_plus_lfb_offset() {
 
     m.write_physical_u32(u64::from(expected_aligned_base), 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
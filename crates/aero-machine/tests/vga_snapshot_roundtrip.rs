@@ -64,7 +64,7 @@ fn vga_snapshot_roundtrip_restores_vbe_and_framebuffer() {
     vm.write_physical_u32(base + 8, 0x0000_00FF); // (2,0) blue
     vm.write_physical_u32(base + 12, 0x00FF_FFFF); // (3,0) white
 
-    vm.display_present();
+    vm.display_present(true);
     let (width, height) = vm.display_resolution();
     let hash_before = framebuffer_hash_rgba8888(vm.display_framebuffer());
 
@@ -74,7 +74,7 @@ fn vga_snapshot_roundtrip_restores_vbe_and_framebuffer() {
     vm2.reset();
     vm2.restore_snapshot_bytes(&snap).unwrap();
 
-    vm2.display_present();
+    vm2.display_present(true);
     assert_eq!(vm2.display_resolution(), (width, height));
     let hash_after = framebuffer_hash_rgba8888(vm2.display_framebuffer());
     assert_eq!(hash_after, hash_before);
@@ -141,7 +141,7 @@ fn vga_snapshot_roundtrip_restores_vbe_and_framebuffer_with_derived_lfb_base() {
     vm.write_physical_u32(base + 8, 0x0000_00FF); // (2,0) blue
     vm.write_physical_u32(base + 12, 0x00FF_FFFF); // (3,0) white
 
-    vm.display_present();
+    vm.display_present(true);
     let (width, height) = vm.display_resolution();
     let hash_before = framebuffer_hash_rgba8888(vm.display_framebuffer());
 
@@ -151,7 +151,7 @@ fn vga_snapshot_roundtrip_restores_vbe_and_framebuffer_with_derived_lfb_base() {
     vm2.reset();
     vm2.restore_snapshot_bytes(&snap).unwrap();
 
-    vm2.display_present();
+    vm2.display_present(true);
     assert_eq!(vm2.display_resolution(), (width, height));
     let hash_after = framebuffer_hash_rgba8888(vm2.display_framebuffer());
     assert_eq!(hash_after, hash_before);
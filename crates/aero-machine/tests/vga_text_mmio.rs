@@ -51,7 +51,7 @@ fn vga_text_mmio_writes_show_up_in_rendered_output() {
     m.write_physical_u8(0xB8000, b'A');
     m.write_physical_u8(0xB8001, 0x1F);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(
         framebuffer_hash(m.display_framebuffer()),
         0x5cfe440e33546065
@@ -54,7 +54,7 @@ fn vga_text_mode_crtc_start_address_offsets_rendered_text() {
     m.io_write(0x3D4, 1, 0x0D);
     m.io_write(0x3D5, 1, 0x00);
 
-    m.display_present();
+    m.display_present(true);
     let pixel_page0 = m.display_framebuffer()[0];
     assert_eq!(pixel_page0, 0xFF00_0000);
 
@@ -64,7 +64,7 @@ fn vga_text_mode_crtc_start_address_offsets_rendered_text() {
     m.io_write(0x3D4, 1, 0x0D);
     m.io_write(0x3D5, 1, 0x00);
 
-    m.display_present();
+    m.display_present(true);
     let pixel_page1 = m.display_framebuffer()[0];
     assert_eq!(pixel_page1, 0xFFAA_0000);
 }
@@ -44,7 +44,7 @@ fn vga_vbe_lfb_is_reachable_via_direct_mmio_without_pc_platform() {
     m.write_physical_u8(base + 2, 0xFF); // R
     m.write_physical_u8(base + 3, 0x00); // X
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 
@@ -59,7 +59,7 @@ fn vga_vbe_lfb_is_reachable_via_direct_mmio_without_pc_platform() {
     m.write_physical_u8(base2 + 6, 0x00); // R
     m.write_physical_u8(base2 + 7, 0x00); // X
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[1], 0xFF00_FF00);
 }
@@ -109,7 +109,7 @@ fn vga_vbe_lfb_base_can_be_derived_from_vram_bar_base_and_lfb_offset_without_pc_
 
     // Write a red pixel at (0,0) in packed 32bpp BGRX via *machine memory*.
     m.write_physical_u32(u64::from(expected_aligned_base), 0x00FF_0000);
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -87,7 +87,7 @@ fn vga_vbe_lfb_is_reachable_via_pci_mmio_router() {
     // Write a red pixel at (0,0) in packed 32bpp BGRX via *machine memory*.
     m.write_physical_u32(base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -144,7 +144,7 @@ fn vga_vbe_lfb_base_can_be_derived_from_vram_bar_base_and_lfb_offset() {
     // Write a red pixel at (0,0) in packed 32bpp BGRX via *machine memory*.
     m.write_physical_u32(base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (64, 64));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
@@ -178,7 +178,7 @@ fn aerogpu_vbe_lfb_is_reachable_via_pci_mmio_router() {
     assert_eq!(base, bar1_base + VBE_LFB_OFFSET as u64);
     m.write_physical_u32(base, 0x00FF_0000);
 
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (1024, 768));
     assert_eq!(m.display_framebuffer()[0], 0xFF00_00FF);
 }
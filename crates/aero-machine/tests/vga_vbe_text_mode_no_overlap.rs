@@ -138,7 +138,7 @@ fn vbe_framebuffer_does_not_clobber_legacy_text_memory() {
 
     // And ensure the rendered output matches the expected glyph (pixel (2,0) is a foreground pixel
     // for 'A' in the built-in font).
-    m.display_present();
+    m.display_present(true);
     assert_eq!(m.display_resolution(), (720, 400));
     assert_eq!(m.display_framebuffer()[2], 0xFFFF_FFFF);
 }
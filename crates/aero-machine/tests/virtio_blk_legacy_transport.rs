@@ -0,0 +1,291 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_devices::pci::profile::{
+    PCI_DEVICE_ID_VIRTIO_BLK_TRANSITIONAL, VIRTIO_BLK, VIRTIO_COMMON_CFG_BAR0_OFFSET,
+    VIRTIO_LEGACY_IO_BAR_INDEX,
+};
+use aero_devices::pci::PciInterruptPin;
+use aero_machine::{Machine, MachineConfig, VirtioPciTransport};
+use aero_platform::interrupts::InterruptController;
+use aero_storage::{MemBackend, RawDisk, VirtualDisk, SECTOR_SIZE};
+use aero_virtio::devices::blk::{VIRTIO_BLK_S_OK, VIRTIO_BLK_T_IN};
+use aero_virtio::pci::{
+    VIRTIO_PCI_LEGACY_GUEST_FEATURES, VIRTIO_PCI_LEGACY_HOST_FEATURES, VIRTIO_PCI_LEGACY_ISR,
+    VIRTIO_PCI_LEGACY_ISR_QUEUE, VIRTIO_PCI_LEGACY_QUEUE_NOTIFY, VIRTIO_PCI_LEGACY_QUEUE_NUM,
+    VIRTIO_PCI_LEGACY_QUEUE_PFN, VIRTIO_PCI_LEGACY_QUEUE_SEL, VIRTIO_PCI_LEGACY_STATUS,
+    VIRTIO_PCI_LEGACY_VRING_ALIGN, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER,
+    VIRTIO_STATUS_DRIVER_OK,
+};
+use aero_virtio::queue::{VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
+use pretty_assertions::assert_eq;
+
+const RING_PFN: u32 = 0x10;
+const HDR: u64 = 0x20000;
+const DATA: u64 = 0x21000;
+const STATUS: u64 = 0x22000;
+const DISK_SECTORS: u64 = 16;
+
+fn cfg(transport: VirtioPciTransport) -> MachineConfig {
+    MachineConfig {
+        ram_size_bytes: 2 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_virtio_blk: true,
+        virtio_blk_transport: transport,
+        // Keep the machine minimal and deterministic for a focused test.
+        enable_ahci: false,
+        enable_nvme: false,
+        enable_ide: false,
+        enable_uhci: false,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    }
+}
+
+/// Disk whose sector `n` is filled with byte `n`.
+fn patterned_disk() -> Box<dyn VirtualDisk> {
+    let mut disk = RawDisk::create(MemBackend::new(), DISK_SECTORS * SECTOR_SIZE as u64).unwrap();
+    for sector in 0..DISK_SECTORS {
+        disk.write_at(sector * SECTOR_SIZE as u64, &[sector as u8; SECTOR_SIZE])
+            .unwrap();
+    }
+    Box::new(disk)
+}
+
+fn new_machine(transport: VirtioPciTransport) -> Machine {
+    let mut m = Machine::new(cfg(transport)).unwrap();
+    m.set_disk_backend(patterned_disk()).unwrap();
+    m
+}
+
+fn pci_cfg_read_u32(m: &mut Machine, reg: u8) -> u32 {
+    let bdf = VIRTIO_BLK.bdf;
+    let addr = 0x8000_0000
+        | (u32::from(bdf.bus) << 16)
+        | (u32::from(bdf.device) << 11)
+        | (u32::from(bdf.function) << 8)
+        | u32::from(reg);
+    m.io_write(0xCF8, 4, addr);
+    m.io_read(0xCFC, 4)
+}
+
+/// Enables I/O decode and bus mastering and returns the legacy register block base (BAR2).
+fn enable_legacy_io(m: &mut Machine) -> u16 {
+    let pci_cfg = m.pci_config_ports().unwrap();
+    let mut pci_cfg = pci_cfg.borrow_mut();
+    let cfg = pci_cfg.bus_mut().device_config_mut(VIRTIO_BLK.bdf).unwrap();
+    // COMMAND.IO | COMMAND.BME
+    cfg.set_command(cfg.command() | (1 << 0) | (1 << 2));
+    let base = cfg
+        .bar_range(VIRTIO_LEGACY_IO_BAR_INDEX)
+        .expect("transitional virtio-blk should expose BAR2")
+        .base;
+    assert_ne!(base, 0, "BAR2 should be assigned by BIOS POST");
+    u16::try_from(base).unwrap()
+}
+
+/// Split virtqueue layout the legacy interface implies for a ring at `pfn`.
+fn legacy_ring(pfn: u32, size: u16) -> (u64, u64, u64) {
+    let desc = u64::from(pfn) << 12;
+    let avail = desc + 16 * u64::from(size);
+    let used =
+        (avail + 4 + 2 * u64::from(size) + 2).next_multiple_of(VIRTIO_PCI_LEGACY_VRING_ALIGN);
+    (desc, avail, used)
+}
+
+fn write_desc(m: &mut Machine, table: u64, index: u16, addr: u64, len: u32, flags: u16, next: u16) {
+    let base = table + u64::from(index) * 16;
+    m.write_physical_u64(base, addr);
+    m.write_physical_u32(base + 8, len);
+    m.write_physical_u16(base + 12, flags);
+    m.write_physical_u16(base + 14, next);
+}
+
+/// Negotiates features and sets up queue 0 through the legacy register block only; returns the
+/// queue size.
+fn legacy_driver_init(m: &mut Machine, io: u16) -> u16 {
+    m.io_write(io + VIRTIO_PCI_LEGACY_STATUS as u16, 1, 0);
+    m.io_write(
+        io + VIRTIO_PCI_LEGACY_STATUS as u16,
+        1,
+        u32::from(VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER),
+    );
+    let host_features = m.io_read(io + VIRTIO_PCI_LEGACY_HOST_FEATURES as u16, 4);
+    m.io_write(
+        io + VIRTIO_PCI_LEGACY_GUEST_FEATURES as u16,
+        4,
+        host_features,
+    );
+
+    m.io_write(io + VIRTIO_PCI_LEGACY_QUEUE_SEL as u16, 2, 0);
+    let size = m.io_read(io + VIRTIO_PCI_LEGACY_QUEUE_NUM as u16, 2) as u16;
+    assert_ne!(size, 0);
+    m.io_write(io + VIRTIO_PCI_LEGACY_QUEUE_PFN as u16, 4, RING_PFN);
+    assert_eq!(
+        m.io_read(io + VIRTIO_PCI_LEGACY_QUEUE_PFN as u16, 4),
+        RING_PFN
+    );
+
+    m.io_write(
+        io + VIRTIO_PCI_LEGACY_STATUS as u16,
+        1,
+        u32::from(VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_DRIVER_OK),
+    );
+    size
+}
+
+/// Posts a one-sector read of `sector` as avail entry `req` and kicks the queue.
+fn submit_read(m: &mut Machine, io: u16, size: u16, req: u16, sector: u64) {
+    let (desc, avail, _) = legacy_ring(RING_PFN, size);
+    m.write_physical_u32(HDR, VIRTIO_BLK_T_IN);
+    m.write_physical_u32(HDR + 4, 0);
+    m.write_physical_u64(HDR + 8, sector);
+    m.write_physical(DATA, &[0xEE; SECTOR_SIZE]);
+    m.write_physical_u8(STATUS, 0xFF);
+
+    write_desc(m, desc, 0, HDR, 16, VIRTQ_DESC_F_NEXT, 1);
+    write_desc(
+        m,
+        desc,
+        1,
+        DATA,
+        SECTOR_SIZE as u32,
+        VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE,
+        2,
+    );
+    write_desc(m, desc, 2, STATUS, 1, VIRTQ_DESC_F_WRITE, 0);
+
+    m.write_physical_u16(avail + 4 + 2 * u64::from(req % size), 0);
+    m.write_physical_u16(avail + 2, req + 1);
+    m.io_write(io + VIRTIO_PCI_LEGACY_QUEUE_NOTIFY as u16, 2, 0);
+}
+
+fn assert_completed(m: &mut Machine, size: u16, used_idx: u16, sector: u64) {
+    let (_, _, used) = legacy_ring(RING_PFN, size);
+    assert_eq!(m.read_physical_u16(used + 2), used_idx);
+    assert_eq!(m.read_physical_u8(STATUS), VIRTIO_BLK_S_OK);
+    assert_eq!(
+        m.read_physical_bytes(DATA, SECTOR_SIZE),
+        vec![sector as u8; SECTOR_SIZE]
+    );
+}
+
+#[test]
+fn modern_virtio_blk_has_no_legacy_io_bar() {
+    let mut m = new_machine(VirtioPciTransport::Modern);
+    let ids = pci_cfg_read_u32(&mut m, 0x00);
+    assert_eq!(ids >> 16, u32::from(VIRTIO_BLK.device_id));
+    assert_eq!(pci_cfg_read_u32(&mut m, 0x18), 0, "BAR2");
+}
+
+#[test]
+fn transitional_virtio_blk_completes_requests_through_legacy_io_bar() {
+    let mut m = new_machine(VirtioPciTransport::Transitional);
+
+    let ids = pci_cfg_read_u32(&mut m, 0x00);
+    assert_eq!(ids >> 16, u32::from(PCI_DEVICE_ID_VIRTIO_BLK_TRANSITIONAL));
+    assert_eq!(pci_cfg_read_u32(&mut m, 0x18) & 1, 1, "BAR2 is an I/O BAR");
+    let io = enable_legacy_io(&mut m);
+
+    // Both views report the same low feature word.
+    let blk = m.virtio_blk().unwrap();
+    let mut modern_features = [0u8; 4];
+    blk.borrow_mut().bar0_read(
+        u64::from(VIRTIO_COMMON_CFG_BAR0_OFFSET) + 0x04,
+        &mut modern_features,
+    );
+    assert_eq!(
+        m.io_read(io + VIRTIO_PCI_LEGACY_HOST_FEATURES as u16, 4),
+        u32::from_le_bytes(modern_features)
+    );
+
+    // Route INTx to a known PIC vector.
+    let gsi = m
+        .pci_intx_router()
+        .unwrap()
+        .borrow()
+        .gsi_for_intx(VIRTIO_BLK.bdf, PciInterruptPin::IntA);
+    let irq = u8::try_from(gsi).expect("virtio-blk INTx should route to a PIC IRQ");
+    assert!(irq < 16);
+    let vector = if irq < 8 {
+        0x20 + irq
+    } else {
+        0x28 + (irq - 8)
+    };
+    let interrupts = m.platform_interrupts().unwrap();
+    {
+        let mut ints = interrupts.borrow_mut();
+        ints.pic_mut().set_offsets(0x20, 0x28);
+        for i in 0..16 {
+            ints.pic_mut().set_masked(i, true);
+        }
+        if irq >= 8 {
+            ints.pic_mut().set_masked(2, false);
+        }
+        ints.pic_mut().set_masked(irq, false);
+    }
+
+    let size = legacy_driver_init(&mut m, io);
+    assert!(blk.borrow().driver_ok());
+
+    submit_read(&mut m, io, size, 0, 3);
+    m.process_virtio_blk();
+    assert_completed(&mut m, size, 1, 3);
+
+    // The completion is only visible to the interrupt controller once INTx levels are synced.
+    assert!(!interrupts.borrow().gsi_level(gsi));
+    m.poll_pci_intx_lines();
+    assert!(interrupts.borrow().gsi_level(gsi));
+    assert_eq!(interrupts.borrow().get_pending(), Some(vector));
+
+    // Reading ISR acknowledges the interrupt: the second read sees nothing pending and the next
+    // INTx sync lowers the line.
+    let isr = m.io_read(io + VIRTIO_PCI_LEGACY_ISR as u16, 1) as u8;
+    assert_eq!(isr, VIRTIO_PCI_LEGACY_ISR_QUEUE);
+    assert_eq!(m.io_read(io + VIRTIO_PCI_LEGACY_ISR as u16, 1), 0);
+    assert!(!blk.borrow().irq_level());
+    m.poll_pci_intx_lines();
+    assert!(!interrupts.borrow().gsi_level(gsi));
+
+    // A second request on the same ring.
+    submit_read(&mut m, io, size, 1, 7);
+    m.process_virtio_blk();
+    assert_completed(&mut m, size, 2, 7);
+    m.poll_pci_intx_lines();
+    assert!(interrupts.borrow().gsi_level(gsi));
+}
+
+#[test]
+fn legacy_bound_virtio_blk_keeps_working_after_snapshot_restore() {
+    let mut m = new_machine(VirtioPciTransport::Transitional);
+    let io = enable_legacy_io(&mut m);
+    let size = legacy_driver_init(&mut m, io);
+    submit_read(&mut m, io, size, 0, 2);
+    m.process_virtio_blk();
+    assert_completed(&mut m, size, 1, 2);
+    assert_eq!(
+        m.io_read(io + VIRTIO_PCI_LEGACY_ISR as u16, 1) as u8,
+        VIRTIO_PCI_LEGACY_ISR_QUEUE
+    );
+
+    let snap = m.take_snapshot_full().unwrap();
+    let mut restored = new_machine(VirtioPciTransport::Transitional);
+    restored.restore_snapshot_bytes(&snap).unwrap();
+
+    assert_eq!(
+        restored.io_read(io + VIRTIO_PCI_LEGACY_QUEUE_PFN as u16, 4),
+        RING_PFN
+    );
+    submit_read(&mut restored, io, size, 1, 5);
+    restored.process_virtio_blk();
+    assert_completed(&mut restored, size, 2, 5);
+    assert_eq!(
+        restored.io_read(io + VIRTIO_PCI_LEGACY_ISR as u16, 1) as u8,
+        VIRTIO_PCI_LEGACY_ISR_QUEUE
+    );
+}
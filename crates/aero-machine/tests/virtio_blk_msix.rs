@@ -1010,3 +1010,43 @@ fn virtio_blk_msix_vector_mask_defers_delivery_until_unmasked() {
         "expected MSI-X pending bit 0 to clear after unmask + delivery"
     );
 }
+
+#[test]
+fn virtio_blk_multi_queue_exposes_one_msix_vector_per_queue() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 4 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_virtio_blk: true,
+        virtio_blk_num_queues: Some(4),
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_a20_gate: false,
+        enable_reset_ctrl: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let bdf = profile::VIRTIO_BLK.bdf;
+
+    let cmd = cfg_read(&mut m, bdf, 0x04, 2) as u16;
+    cfg_write(&mut m, bdf, 0x04, 2, u32::from(cmd | (1 << 1)));
+    let bar0_lo = cfg_read(&mut m, bdf, 0x10, 4) as u64;
+    let bar0_hi = cfg_read(&mut m, bdf, 0x14, 4) as u64;
+    let bar0_base = (bar0_hi << 32) | (bar0_lo & !0xFu64);
+    assert_ne!(bar0_base, 0, "expected virtio-blk BAR0 to be assigned");
+
+    // Table Size is N-1: four request queues plus the config vector.
+    let msix_cap = find_capability(&mut m, bdf, aero_devices::pci::msix::PCI_CAP_ID_MSIX)
+        .expect("virtio-blk should expose MSI-X capability");
+    let ctrl = cfg_read(&mut m, bdf, msix_cap + 0x02, 2) as u16;
+    assert_eq!(ctrl & 0x7ff, 4);
+
+    const COMMON: u64 = profile::VIRTIO_COMMON_CFG_BAR0_OFFSET as u64;
+    const DEVICE: u64 = profile::VIRTIO_DEVICE_CFG_BAR0_OFFSET as u64;
+    m.write_physical_u32(bar0_base + COMMON, 0);
+    let f0 = m.read_physical_u32(bar0_base + COMMON + 0x04);
+    assert_ne!(f0 & (1 << 12), 0, "VIRTIO_BLK_F_MQ must be offered");
+    assert_ne!(f0 & (1 << 1), 0, "VIRTIO_BLK_F_SIZE_MAX must be offered");
+    assert_eq!(m.read_physical_u16(bar0_base + COMMON + 0x12), 4);
+    assert_eq!(m.read_physical_u16(bar0_base + DEVICE + 34), 4);
+}
@@ -0,0 +1,207 @@
+use aero_cpu_core::state::CpuState;
+use aero_machine::{Machine, MachineConfig, PageTableLevel, VirtTranslateError};
+use pretty_assertions::assert_eq;
+
+const CR0_PE: u64 = 1 << 0;
+const CR0_WP: u64 = 1 << 16;
+const CR0_PG: u64 = 1 << 31;
+const CR4_PSE: u64 = 1 << 4;
+const CR4_PAE: u64 = 1 << 5;
+const EFER_LME: u64 = 1 << 8;
+const EFER_LMA: u64 = 1 << 10;
+
+const P: u64 = 1 << 0;
+const RW: u64 = 1 << 1;
+const PS: u64 = 1 << 7;
+const ACCESSED: u64 = 1 << 5;
+const NX: u64 = 1 << 63;
+
+fn new_machine() -> Machine {
+    Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        cpu_count: 2,
+        enable_pc_platform: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn set_paging(state: &mut CpuState, cr0: u64, cr3: u64, cr4: u64, efer: u64) {
+    state.control.cr0 = cr0;
+    state.control.cr3 = cr3;
+    state.control.cr4 = cr4;
+    state.msr.efer = efer;
+}
+
+#[test]
+fn legacy32_translations_reads_and_writes() {
+    let mut m = new_machine();
+    let pd = 0x10_0000u64;
+    let pt = 0x10_1000u64;
+    m.write_physical_u32(pd, (pt | P | RW) as u32);
+    // 4MB page: 0x0040_0000.. -> 0x0080_0000..
+    m.write_physical_u32(pd + 4, (0x80_0000 | P | RW | PS) as u32);
+    // Read-only 4KB page at 0x5000 and a non-contiguous neighbour at 0x6000.
+    m.write_physical_u32(pt + 5 * 4, (0x20_0000 | P) as u32);
+    m.write_physical_u32(pt + 6 * 4, (0x30_0000 | P | RW) as u32);
+    set_paging(m.cpu_mut(), CR0_PE | CR0_PG | CR0_WP, pd, CR4_PSE, 0);
+
+    assert_eq!(m.translate_virtual(0, 0x5123), Ok(0x20_0123));
+    assert_eq!(m.translate_virtual(0, 0x40_1234), Ok(0x80_1234));
+    assert_eq!(
+        m.translate_virtual(0, 0x7000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x7000,
+            level: PageTableLevel::Pt,
+        })
+    );
+    assert_eq!(
+        m.translate_virtual(0, 0x80_0000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x80_0000,
+            level: PageTableLevel::Pd,
+        })
+    );
+
+    // Reads split at the page boundary.
+    m.write_physical(0x20_0FFC, &[1, 2, 3, 4]);
+    m.write_physical(0x30_0000, &[5, 6, 7, 8]);
+    assert_eq!(
+        m.read_virtual_bytes(0, 0x5FFC, 8),
+        Ok(vec![1, 2, 3, 4, 5, 6, 7, 8])
+    );
+    assert_eq!(
+        m.read_virtual_bytes(0, 0x6FFE, 4),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x7000,
+            level: PageTableLevel::Pt,
+        })
+    );
+
+    // CR0.WP protects the read-only page unless the caller opts into bypassing permissions. A
+    // rejected write spanning two pages writes nothing.
+    assert_eq!(
+        m.write_virtual_bytes(0, 0x5FFE, &[0xAA; 4], false),
+        Err(VirtTranslateError::WriteProtected {
+            vaddr: 0x5FFE,
+            level: PageTableLevel::Pt,
+        })
+    );
+    assert_eq!(m.read_physical_bytes(0x30_0000, 2), [5, 6]);
+    assert_eq!(
+        m.write_virtual_bytes(0, 0x6000, &[0x11, 0x22], false),
+        Ok(())
+    );
+    assert_eq!(m.write_virtual_bytes(0, 0x5FFE, &[0xAA; 4], true), Ok(()));
+    assert_eq!(m.read_physical_bytes(0x20_0FFE, 2), [0xAA, 0xAA]);
+    assert_eq!(m.read_physical_bytes(0x30_0000, 4), [0xAA, 0xAA, 7, 8]);
+
+    // Debug walks leave the guest's accessed bits alone.
+    assert_eq!(u64::from(m.read_physical_u32(pd)) & ACCESSED, 0);
+    assert_eq!(u64::from(m.read_physical_u32(pt + 5 * 4)) & ACCESSED, 0);
+}
+
+#[test]
+fn pae_translations_and_reserved_bit_faults() {
+    let mut m = new_machine();
+    let pdpt = 0x10_0000u64;
+    let pd = 0x10_1000u64;
+    let pt = 0x10_2000u64;
+    m.write_physical_u64(pdpt, pd | P);
+    m.write_physical_u64(pd, pt | P | RW);
+    // 2MB page: 0x0020_0000.. -> 0x0060_0000..
+    m.write_physical_u64(pd + 8, 0x60_0000 | P | RW | PS);
+    m.write_physical_u64(pt + 8, 0x20_0000 | P | RW);
+    // NX without EFER.NXE is a reserved bit.
+    m.write_physical_u64(pt + 2 * 8, 0x30_0000 | P | RW | NX);
+    set_paging(m.cpu_mut(), CR0_PE | CR0_PG, pdpt, CR4_PAE | CR4_PSE, 0);
+
+    assert_eq!(m.translate_virtual(0, 0x1ABC), Ok(0x20_0ABC));
+    assert_eq!(m.translate_virtual(0, 0x2F_FFFF), Ok(0x6F_FFFF));
+    assert_eq!(
+        m.translate_virtual(0, 0x2000),
+        Err(VirtTranslateError::ReservedBits {
+            vaddr: 0x2000,
+            level: PageTableLevel::Pt,
+        })
+    );
+    assert_eq!(
+        m.translate_virtual(0, 0x4000_0000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x4000_0000,
+            level: PageTableLevel::Pdpt,
+        })
+    );
+
+    m.write_physical(0x20_0ABC, b"pae");
+    assert_eq!(m.read_virtual_bytes(0, 0x1ABC, 3).unwrap(), b"pae");
+}
+
+#[test]
+fn long_mode_translations_use_the_selected_vcpu() {
+    let mut m = new_machine();
+    let pml4 = 0x10_0000u64;
+    let pdpt = 0x10_1000u64;
+    let pd = 0x10_2000u64;
+    let pt = 0x10_3000u64;
+    let pdpt_high = 0x10_4000u64;
+    m.write_physical_u64(pml4, pdpt | P | RW);
+    m.write_physical_u64(pml4 + 511 * 8, pdpt_high | P | RW);
+    m.write_physical_u64(pdpt, pd | P | RW);
+    // 1GB page: 0x4000_0000.. -> 0x1_0000_0000..
+    m.write_physical_u64(pdpt + 8, 0x1_0000_0000 | P | RW | PS);
+    m.write_physical_u64(pd, pt | P | RW);
+    // 2MB page: 0x0020_0000.. -> 0x0080_0000..
+    m.write_physical_u64(pd + 8, 0x80_0000 | P | RW | PS);
+    m.write_physical_u64(pt + 3 * 8, 0x50_0000 | P | RW);
+    set_paging(
+        &mut m.cpu_core_mut_by_index(1).state,
+        CR0_PE | CR0_PG,
+        pml4,
+        CR4_PAE | CR4_PSE,
+        EFER_LME | EFER_LMA,
+    );
+
+    assert_eq!(m.translate_virtual(1, 0x3010), Ok(0x50_0010));
+    assert_eq!(m.translate_virtual(1, 0x20_1234), Ok(0x80_1234));
+    assert_eq!(m.translate_virtual(1, 0x7FFF_FFFF), Ok(0x1_3FFF_FFFF));
+    assert_eq!(
+        m.translate_virtual(1, 0x4000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x4000,
+            level: PageTableLevel::Pt,
+        })
+    );
+    assert_eq!(
+        m.translate_virtual(1, 0x80_0000_0000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0x80_0000_0000,
+            level: PageTableLevel::Pml4,
+        })
+    );
+    assert_eq!(
+        m.translate_virtual(1, 0xFFFF_FF80_0000_0000),
+        Err(VirtTranslateError::NotPresent {
+            vaddr: 0xFFFF_FF80_0000_0000,
+            level: PageTableLevel::Pdpt,
+        })
+    );
+    assert_eq!(
+        m.translate_virtual(1, 0x0000_8000_0000_0000),
+        Err(VirtTranslateError::NonCanonical(0x0000_8000_0000_0000))
+    );
+
+    m.write_physical(0x50_0010, b"hello");
+    assert_eq!(m.read_virtual_bytes(1, 0x3010, 5).unwrap(), b"hello");
+
+    // The BSP still runs without paging, so its view is the identity mapping.
+    assert_eq!(m.translate_virtual(0, 0x3010), Ok(0x3010));
+    assert_eq!(
+        m.translate_virtual(2, 0x3010),
+        Err(VirtTranslateError::InvalidCpuIndex(2))
+    );
+}
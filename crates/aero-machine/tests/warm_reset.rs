@@ -0,0 +1,169 @@
+use aero_machine::{Machine, MachineConfig, RunExit};
+use aero_platform::reset::ResetKind;
+use pretty_assertions::assert_eq;
+
+const RESUME_MARKER_ADDR: u64 = 0x2000;
+const RAM_MARKER_ADDR: u64 = 0x3000;
+
+fn boot_sector(code: &[u8]) -> Vec<u8> {
+    let mut sector = vec![0u8; aero_storage::SECTOR_SIZE];
+    sector[..code.len()].copy_from_slice(code);
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+/// Boot sector that arms a CMOS shutdown-status resume (code 0x0A via 40:67) and requests a CPU
+/// reset through port 0xCF9. The resume target writes a marker and halts.
+fn shutdown_resume_boot_sector() -> Vec<u8> {
+    const RESUME_OFFSET: u16 = 0x7C00 + 37;
+    let [resume_lo, resume_hi] = RESUME_OFFSET.to_le_bytes();
+    let code = [
+        0xFA, // cli
+        0x31, 0xC0, // xor ax, ax
+        0x8E, 0xD8, // mov ds, ax
+        0x8E, 0xD0, // mov ss, ax
+        0xBC, 0x00, 0x7C, // mov sp, 0x7C00
+        0xC7, 0x06, 0x67, 0x04, resume_lo, resume_hi, // mov word [0x467], resume
+        0xC7, 0x06, 0x69, 0x04, 0x00, 0x00, // mov word [0x469], 0
+        0xB0, 0x0F, 0xE6, 0x70, // mov al, 0x0F; out 0x70, al
+        0xB0, 0x0A, 0xE6, 0x71, // mov al, 0x0A; out 0x71, al
+        0xBA, 0xF9, 0x0C, // mov dx, 0xCF9
+        0xB0, 0x05, // mov al, 0x05 (reset enable | CPU reset)
+        0xEE, // out dx, al
+        0xF4, // hlt
+        // resume:
+        0xC6, 0x06, 0x00, 0x20, 0xA5, // mov byte [0x2000], 0xA5
+        0xF4, // hlt
+    ];
+    assert_eq!(code[37], 0xC6);
+    boot_sector(&code)
+}
+
+fn new_machine() -> Machine {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 16 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(shutdown_resume_boot_sector()).unwrap();
+    m.reset();
+    m
+}
+
+fn cmos_read(m: &mut Machine, reg: u8) -> u8 {
+    m.io_write(0x70, 1, u32::from(reg));
+    m.io_read(0x71, 1) as u8
+}
+
+fn cmos_write(m: &mut Machine, reg: u8, value: u8) {
+    m.io_write(0x70, 1, u32::from(reg));
+    m.io_write(0x71, 1, u32::from(value));
+}
+
+fn run_until_reset_request(m: &mut Machine) -> ResetKind {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::ResetRequested { kind, .. } => return kind,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not request a reset");
+}
+
+fn run_until_halt(m: &mut Machine) {
+    for _ in 0..100 {
+        match m.run_slice(10_000) {
+            RunExit::Halted { .. } => return,
+            RunExit::Completed { .. } => {}
+            other => panic!("unexpected exit: {other:?}"),
+        }
+    }
+    panic!("guest did not reach HLT");
+}
+
+#[test]
+fn warm_reset_resumes_through_cmos_shutdown_status() {
+    let mut m = new_machine();
+    let kind = run_until_reset_request(&mut m);
+    assert_eq!(kind, ResetKind::Cpu);
+
+    m.write_physical_u8(RAM_MARKER_ADDR, 0x5A);
+    m.reset_with_kind(kind);
+
+    // Firmware skipped POST (the boot sector was not reloaded) and jumped via 40:67.
+    assert_eq!(m.cpu().segments.cs.selector, 0);
+    assert_eq!(m.cpu().rip(), 0x7C00 + 37);
+    run_until_halt(&mut m);
+    assert_eq!(m.read_physical_u8(RESUME_MARKER_ADDR), 0xA5);
+    assert_eq!(m.read_physical_u8(RAM_MARKER_ADDR), 0x5A);
+    // The shutdown status is consumed, so the next reset runs POST again.
+    assert_eq!(cmos_read(&mut m, 0x0F), 0);
+}
+
+#[test]
+fn cold_reset_ignores_cmos_shutdown_status() {
+    let mut m = new_machine();
+    run_until_reset_request(&mut m);
+
+    m.reset_with_kind(ResetKind::System);
+    // POST reloaded the boot sector, which arms the resume and requests a reset again.
+    assert_eq!(m.cpu().rip(), 0x7C00);
+    assert_eq!(run_until_reset_request(&mut m), ResetKind::Cpu);
+    assert_eq!(m.read_physical_u8(RESUME_MARKER_ADDR), 0);
+}
+
+#[test]
+fn warm_reset_preserves_cmos_and_rtc_time() {
+    let mut m = new_machine();
+    cmos_write(&mut m, 0x40, 0x77);
+    // Set the RTC to 00:42:00 (BCD) while updates are inhibited.
+    let reg_b = cmos_read(&mut m, 0x0B);
+    cmos_write(&mut m, 0x0B, reg_b | 0x80);
+    cmos_write(&mut m, 0x02, 0x42);
+    cmos_write(&mut m, 0x0B, reg_b);
+
+    m.reset_with_kind(ResetKind::Cpu);
+    assert_eq!(cmos_read(&mut m, 0x40), 0x77);
+    assert_eq!(cmos_read(&mut m, 0x02), 0x42);
+
+    m.reset_with_kind(ResetKind::System);
+    assert_eq!(cmos_read(&mut m, 0x40), 0);
+    assert_eq!(cmos_read(&mut m, 0x02), 0);
+}
+
+#[test]
+fn warm_reset_keeps_aerogpu_vram() {
+    let mut m = Machine::new(MachineConfig {
+        ram_size_bytes: 64 * 1024 * 1024,
+        enable_pc_platform: true,
+        enable_vga: false,
+        enable_aerogpu: true,
+        enable_serial: false,
+        enable_i8042: false,
+        enable_e1000: false,
+        enable_virtio_net: false,
+        ..Default::default()
+    })
+    .unwrap();
+    m.set_disk_image(boot_sector(&[0xF4])).unwrap();
+    m.reset();
+    let vram = m
+        .aerogpu_vram_bar_base()
+        .expect("AeroGPU BAR1 base should be assigned");
+    let addr = vram + 0x20_0000;
+
+    m.write_physical_u32(addr, 0x1234_5678);
+    m.reset_with_kind(ResetKind::Cpu);
+    assert_eq!(m.read_physical_u32(addr), 0x1234_5678);
+
+    m.reset_with_kind(ResetKind::System);
+    assert_eq!(m.read_physical_u32(addr), 0);
+}
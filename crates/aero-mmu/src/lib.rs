@@ -377,6 +377,46 @@ impl PageFault {
     fn new(addr: u64, error_code: u32) -> Self {
         Self { addr, error_code }
     }
+
+    #[inline]
+    fn at(self, level: PageTableLevel) -> WalkFault {
+        WalkFault::PageFault { fault: self, level }
+    }
+}
+
+/// Paging-structure level of a page-table walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageTableLevel {
+    /// PML4 (long mode only).
+    Pml4,
+    /// Page-directory-pointer table (PAE and long mode); maps 1GB pages in long mode.
+    Pdpt,
+    /// Page directory; maps 4MB (32-bit) or 2MB (PAE/long mode) pages.
+    Pd,
+    /// Page table; maps 4KB pages.
+    Pt,
+}
+
+/// A failed [`Mmu::walk_probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkFault {
+    /// The #PF the access would raise, and the level whose entry caused it (for permission faults,
+    /// the level holding the leaf entry).
+    PageFault {
+        fault: PageFault,
+        level: PageTableLevel,
+    },
+    /// Non-canonical linear address in long mode (would raise #GP(0)).
+    NonCanonical(u64),
+}
+
+impl From<WalkFault> for TranslateFault {
+    fn from(fault: WalkFault) -> Self {
+        match fault {
+            WalkFault::PageFault { fault, .. } => TranslateFault::PageFault(fault),
+            WalkFault::NonCanonical(vaddr) => TranslateFault::NonCanonical(vaddr),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -1117,7 +1157,36 @@ impl Mmu {
             PagingMode::Long4 => self.walk_long4_probe(bus, vaddr, access, is_user),
         };
 
-        walk_res.map_err(TranslateFault::PageFault)
+        walk_res.map_err(TranslateFault::from)
+    }
+
+    /// Walk the guest page tables for `vaddr` with the same checks as [`Mmu::translate_probe`],
+    /// but without consulting the TLB, and report which paging-structure level stopped a failed
+    /// walk.
+    ///
+    /// Neither guest memory nor the TLB is modified (CR2 is not updated either), so debuggers can
+    /// translate addresses without disturbing execution.
+    pub fn walk_probe(
+        &mut self,
+        bus: &mut impl MemoryBus,
+        vaddr: u64,
+        access: AccessType,
+        cpl: u8,
+    ) -> Result<u64, WalkFault> {
+        let is_user = cpl == 3;
+        match self.paging_mode() {
+            PagingMode::Disabled => Ok(vaddr & 0xffff_ffff),
+            PagingMode::Legacy32 => {
+                self.walk_legacy32_probe(bus, vaddr as u32 as u64, access, is_user)
+            }
+            PagingMode::Pae => self.walk_pae_probe(bus, vaddr as u32 as u64, access, is_user),
+            PagingMode::Long4 => {
+                if !is_canonical_48(vaddr) {
+                    return Err(WalkFault::NonCanonical(vaddr));
+                }
+                self.walk_long4_probe(bus, vaddr, access, is_user)
+            }
+        }
     }
 
     #[inline]
@@ -1332,23 +1401,29 @@ impl Mmu {
         vaddr: u64,
         access: AccessType,
         is_user: bool,
-    ) -> Result<u64, PageFault> {
+    ) -> Result<u64, WalkFault> {
         let pd_base = (self.cr3 & 0xffff_ffff) & !0xfff;
         let pd_index = (vaddr >> 22) & 0x3ff;
         let pde_addr = pd_base + pd_index * 4;
         let pde_raw = bus.read_u32(pde_addr) as u64;
         if pde_raw & PTE_P == 0 {
-            return Err(self.page_fault_not_present(vaddr, access, is_user));
+            return Err(self
+                .page_fault_not_present(vaddr, access, is_user)
+                .at(PageTableLevel::Pd));
         }
 
         let pde_ps = pde_raw & PTE_PS != 0;
         if pde_ps {
             // 4MB pages require CR4.PSE; otherwise PS is treated as reserved.
             if !self.cr4_pse() {
-                return Err(self.page_fault_rsvd(vaddr, access, is_user));
+                return Err(self
+                    .page_fault_rsvd(vaddr, access, is_user)
+                    .at(PageTableLevel::Pd));
             }
             if (pde_raw & LEGACY32_4MB_RESERVED_MASK) != 0 {
-                return Err(self.page_fault_rsvd(vaddr, access, is_user));
+                return Err(self
+                    .page_fault_rsvd(vaddr, access, is_user)
+                    .at(PageTableLevel::Pd));
             }
 
             let pde = pde_raw;
@@ -1357,7 +1432,8 @@ impl Mmu {
             let writable_ok = pde & PTE_RW != 0;
             let nx = false;
 
-            self.check_perms(vaddr, user_ok, writable_ok, nx, access, is_user)?;
+            self.check_perms(vaddr, user_ok, writable_ok, nx, access, is_user)
+                .map_err(|pf| pf.at(PageTableLevel::Pd))?;
 
             let page_size = PageSize::Size4M;
             let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1374,14 +1450,19 @@ impl Mmu {
         let pte_raw = bus.read_u32(pte_addr) as u64;
         let pte = match self.check_entry32_probe(pte_raw) {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pt))
+            }
         };
 
         let user_ok = (pde & PTE_US != 0) && (pte & PTE_US != 0);
         let writable_ok = (pde & PTE_RW != 0) && (pte & PTE_RW != 0);
         let nx = false;
 
-        self.check_perms(vaddr, user_ok, writable_ok, nx, access, is_user)?;
+        self.check_perms(vaddr, user_ok, writable_ok, nx, access, is_user)
+            .map_err(|pf| pf.at(PageTableLevel::Pt))?;
 
         let page_size = PageSize::Size4K;
         let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1526,7 +1607,7 @@ impl Mmu {
         vaddr: u64,
         access: AccessType,
         is_user: bool,
-    ) -> Result<u64, PageFault> {
+    ) -> Result<u64, WalkFault> {
         let nx_enabled = self.nx_enabled();
         let addr_mask = self.phys_addr_mask();
         let ctx = EntryAccessContext {
@@ -1540,9 +1621,16 @@ impl Mmu {
         let pdpte_addr = pdpt_base + pdpt_index * 8;
         let pdpte = bus.read_u64(pdpte_addr);
 
-        let pdpte = match self.check_entry64_probe(pdpte, ctx, EntryKind64::PdptePae)? {
+        let pdpte = match self
+            .check_entry64_probe(pdpte, ctx, EntryKind64::PdptePae)
+            .map_err(|pf| pf.at(PageTableLevel::Pdpt))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pdpt))
+            }
         };
 
         // In IA-32 PAE paging, the PDPT entry does not participate in U/S or
@@ -1557,9 +1645,16 @@ impl Mmu {
         let pde_addr = pd_base + pd_index * 8;
         let pde = bus.read_u64(pde_addr);
 
-        let pde = match self.check_entry64_probe(pde, ctx, EntryKind64::PdePae)? {
+        let pde = match self
+            .check_entry64_probe(pde, ctx, EntryKind64::PdePae)
+            .map_err(|pf| pf.at(PageTableLevel::Pd))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pd))
+            }
         };
 
         eff_user &= pde & PTE_US64 != 0;
@@ -1568,7 +1663,8 @@ impl Mmu {
 
         let pde_ps = pde & PTE_PS64 != 0;
         if pde_ps {
-            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)?;
+            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)
+                .map_err(|pf| pf.at(PageTableLevel::Pd))?;
 
             let page_size = PageSize::Size2M;
             let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1582,16 +1678,24 @@ impl Mmu {
         let pte_addr = pt_base + pt_index * 8;
         let pte = bus.read_u64(pte_addr);
 
-        let pte = match self.check_entry64_probe(pte, ctx, EntryKind64::PtePae)? {
+        let pte = match self
+            .check_entry64_probe(pte, ctx, EntryKind64::PtePae)
+            .map_err(|pf| pf.at(PageTableLevel::Pt))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pt))
+            }
         };
 
         eff_user &= pte & PTE_US64 != 0;
         eff_writable &= pte & PTE_RW64 != 0;
         eff_nx |= nx_enabled && (pte & PTE_NX != 0);
 
-        self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)?;
+        self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)
+            .map_err(|pf| pf.at(PageTableLevel::Pt))?;
 
         let page_size = PageSize::Size4K;
         let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1783,7 +1887,7 @@ impl Mmu {
         vaddr: u64,
         access: AccessType,
         is_user: bool,
-    ) -> Result<u64, PageFault> {
+    ) -> Result<u64, WalkFault> {
         let nx_enabled = self.nx_enabled();
         let addr_mask = self.phys_addr_mask();
         let ctx = EntryAccessContext {
@@ -1797,9 +1901,16 @@ impl Mmu {
         let pml4e_addr = pml4_base + pml4_index * 8;
         let pml4e = bus.read_u64(pml4e_addr);
 
-        let pml4e = match self.check_entry64_probe(pml4e, ctx, EntryKind64::Pml4e)? {
+        let pml4e = match self
+            .check_entry64_probe(pml4e, ctx, EntryKind64::Pml4e)
+            .map_err(|pf| pf.at(PageTableLevel::Pml4))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pml4))
+            }
         };
 
         let mut eff_user = pml4e & PTE_US64 != 0;
@@ -1811,9 +1922,16 @@ impl Mmu {
         let pdpte_addr = pdpt_base + pdpt_index * 8;
         let pdpte = bus.read_u64(pdpte_addr);
 
-        let pdpte = match self.check_entry64_probe(pdpte, ctx, EntryKind64::PdpteLong)? {
+        let pdpte = match self
+            .check_entry64_probe(pdpte, ctx, EntryKind64::PdpteLong)
+            .map_err(|pf| pf.at(PageTableLevel::Pdpt))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pdpt))
+            }
         };
 
         eff_user &= pdpte & PTE_US64 != 0;
@@ -1822,7 +1940,8 @@ impl Mmu {
 
         let pdpte_ps = pdpte & PTE_PS64 != 0;
         if pdpte_ps {
-            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)?;
+            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)
+                .map_err(|pf| pf.at(PageTableLevel::Pdpt))?;
 
             let page_size = PageSize::Size1G;
             let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1836,9 +1955,16 @@ impl Mmu {
         let pde_addr = pd_base + pd_index * 8;
         let pde = bus.read_u64(pde_addr);
 
-        let pde = match self.check_entry64_probe(pde, ctx, EntryKind64::PdeLong)? {
+        let pde = match self
+            .check_entry64_probe(pde, ctx, EntryKind64::PdeLong)
+            .map_err(|pf| pf.at(PageTableLevel::Pd))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pd))
+            }
         };
 
         eff_user &= pde & PTE_US64 != 0;
@@ -1847,7 +1973,8 @@ impl Mmu {
 
         let pde_ps = pde & PTE_PS64 != 0;
         if pde_ps {
-            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)?;
+            self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)
+                .map_err(|pf| pf.at(PageTableLevel::Pd))?;
 
             let page_size = PageSize::Size2M;
             let vbase = vaddr & !(page_size.bytes() - 1);
@@ -1861,16 +1988,24 @@ impl Mmu {
         let pte_addr = pt_base + pt_index * 8;
         let pte = bus.read_u64(pte_addr);
 
-        let pte = match self.check_entry64_probe(pte, ctx, EntryKind64::PteLong)? {
+        let pte = match self
+            .check_entry64_probe(pte, ctx, EntryKind64::PteLong)
+            .map_err(|pf| pf.at(PageTableLevel::Pt))?
+        {
             Some(v) => v,
-            None => return Err(self.page_fault_not_present(vaddr, access, is_user)),
+            None => {
+                return Err(self
+                    .page_fault_not_present(vaddr, access, is_user)
+                    .at(PageTableLevel::Pt))
+            }
         };
 
         eff_user &= pte & PTE_US64 != 0;
         eff_writable &= pte & PTE_RW64 != 0;
         eff_nx |= nx_enabled && (pte & PTE_NX != 0);
 
-        self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)?;
+        self.check_perms(vaddr, eff_user, eff_writable, eff_nx, access, is_user)
+            .map_err(|pf| pf.at(PageTableLevel::Pt))?;
 
         let page_size = PageSize::Size4K;
         let vbase = vaddr & !(page_size.bytes() - 1);
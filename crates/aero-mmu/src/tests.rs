@@ -1719,6 +1719,71 @@ fn translate_probe_does_not_populate_tlb_on_miss_long4() {
     );
 }
 
+#[test]
+fn walk_probe_bypasses_tlb_and_reports_faulting_level_long4() {
+    let mut mmu = Mmu::new();
+    let mut mem = TestMemory::new(0x40000);
+
+    let pml4_base = 0x1000u64;
+    let pdpt_base = 0x2000u64;
+    let pd_base = 0x3000u64;
+    let pt_base = 0x4000u64;
+    let page_base = 0x8000u64;
+
+    let vaddr = 0x2345u64;
+    let pd_index = (vaddr >> 21) & 0x1ff;
+    let pt_index = (vaddr >> 12) & 0x1ff;
+
+    mem.write_u64_raw(pml4_base, pdpt_base | PTE_P64 | PTE_RW64);
+    mem.write_u64_raw(pdpt_base, pd_base | PTE_P64 | PTE_RW64);
+    mem.write_u64_raw(pd_base + pd_index * 8, pt_base | PTE_P64 | PTE_RW64);
+    mem.write_u64_raw(pt_base + pt_index * 8, page_base | PTE_P64 | PTE_RW64);
+
+    mmu.set_cr3(pml4_base);
+    mmu.set_cr4(CR4_PAE);
+    mmu.set_efer(EFER_LME);
+    mmu.set_cr0(CR0_PG);
+
+    // Populate the TLB, then unmap the page without INVLPG: the TLB still translates it, but a
+    // walk sees the page tables as they are.
+    assert_eq!(
+        mmu.translate(&mut mem, vaddr, AccessType::Read, 0),
+        Ok(page_base + 0x345)
+    );
+    mem.write_u64_raw(pt_base + pt_index * 8, 0);
+    assert_eq!(
+        mmu.translate_probe(&mut mem, vaddr, AccessType::Read, 0),
+        Ok(page_base + 0x345)
+    );
+
+    mem.reset_counters();
+    let not_present = |level| {
+        Err(WalkFault::PageFault {
+            fault: PageFault {
+                addr: vaddr,
+                error_code: 0,
+            },
+            level,
+        })
+    };
+    assert_eq!(
+        mmu.walk_probe(&mut mem, vaddr, AccessType::Read, 0),
+        not_present(PageTableLevel::Pt)
+    );
+    mem.write_u64_raw(pd_base + pd_index * 8, 0);
+    assert_eq!(
+        mmu.walk_probe(&mut mem, vaddr, AccessType::Read, 0),
+        not_present(PageTableLevel::Pd)
+    );
+    assert_eq!(mem.writes(), 0);
+    assert_eq!(mmu.cr2(), 0);
+
+    assert_eq!(
+        mmu.walk_probe(&mut mem, 0x0000_8000_0000_0000, AccessType::Read, 0),
+        Err(WalkFault::NonCanonical(0x0000_8000_0000_0000))
+    );
+}
+
 #[cfg(not(feature = "stats"))]
 #[test]
 fn stats_is_none_without_feature() {
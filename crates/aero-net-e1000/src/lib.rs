@@ -116,8 +116,44 @@ const EERD_ADDR_SHIFT: u32 = 8;
 const EERD_DATA_SHIFT: u32 = 16;
 
 // EECD bits (subset).
+const EECD_SK: u32 = 1 << 0;
+const EECD_CS: u32 = 1 << 1;
+const EECD_DI: u32 = 1 << 2;
+const EECD_DO: u32 = 1 << 3;
+const EECD_EE_REQ: u32 = 1 << 6;
+const EECD_EE_GNT: u32 = 1 << 7;
 const EECD_EE_PRES: u32 = 1 << 8;
 
+/// Microwire READ opcode, including the leading start bit (`1` + `10`).
+const MICROWIRE_OPCODE_READ: u32 = 0b110;
+/// Start bit + 2-bit opcode + 6-bit word address (93C46-style 64 x 16-bit part).
+const MICROWIRE_CMD_BITS: u32 = 9;
+
+/// EEPROM word holding the checksum; words `0..=EEPROM_CHECKSUM_WORD` sum to [`EEPROM_SUM`].
+pub const EEPROM_CHECKSUM_WORD: usize = 0x3F;
+/// Expected 16-bit wrapping sum of the whole EEPROM image (Intel NVM convention).
+pub const EEPROM_SUM: u16 = 0xBABA;
+
+// MII / Marvell 88E1000-family PHY registers.
+const PHY_BMCR: usize = 0;
+const PHY_BMSR: usize = 1;
+const PHY_ID1: usize = 2;
+const PHY_ID2: usize = 3;
+const PHY_ANAR: usize = 4;
+const PHY_ANLPAR: usize = 5;
+const PHY_1000T_CTRL: usize = 9;
+const PHY_1000T_STATUS: usize = 10;
+const PHY_EXT_STATUS: usize = 15;
+const PHY_M88_SPEC_CTRL: usize = 16;
+const PHY_M88_SPEC_STATUS: usize = 17;
+
+const PHY_BMCR_RESET: u16 = 1 << 15;
+const PHY_BMCR_RESTART_AN: u16 = 1 << 9;
+const PHY_BMSR_LINK: u16 = 1 << 2;
+const PHY_BMSR_AN_COMPLETE: u16 = 1 << 5;
+/// M88 PHY Specific Status: 1000Mb/s, full duplex, speed/duplex resolved, link up.
+const PHY_M88_STATUS_LINK_1000FD: u16 = 0x8000 | 0x2000 | 0x0800 | 0x0400;
+
 // MDIC bits/fields (subset).
 const MDIC_DATA_MASK: u32 = 0x0000_FFFF;
 const MDIC_REG_SHIFT: u32 = 16;
@@ -130,6 +166,7 @@ const MDIC_READY: u32 = 0x1000_0000;
 
 // Interrupt Cause bits (subset).
 pub const ICR_TXDW: u32 = 1 << 0;
+pub const ICR_LSC: u32 = 1 << 2;
 pub const ICR_RXT0: u32 = 1 << 7;
 
 // RCTL bits (subset).
@@ -331,6 +368,22 @@ enum TxPacketState {
     Advanced { cmd: u8, popts: u8 },
 }
 
+/// Shift-register state of the bit-banged Microwire EEPROM interface.
+///
+/// While CS is high, the driver clocks in a start bit, a 2-bit opcode and a 6-bit word address
+/// on SK rising edges; for READ the EEPROM then shifts the addressed word (and any following
+/// words) out on DO, MSB first, advancing one bit per SK falling edge.
+///
+/// This is transient bus state and is not snapshotted; a restore abandons any in-flight access.
+#[derive(Debug, Clone, Copy, Default)]
+struct MicrowireState {
+    shift_in: u32,
+    bits_in: u32,
+    /// Absolute bit index into the EEPROM image of the bit currently driven on DO.
+    bit_out: u32,
+    reading: bool,
+}
+
 fn read_desc<const N: usize>(mem: &mut dyn MemoryBus, addr: u64) -> [u8; N] {
     let mut buf = [0u8; N];
     mem.read_physical(addr, &mut buf);
@@ -668,6 +721,11 @@ pub struct E1000Device {
     phy: [u16; 32],
     other_regs: HashMap<u32, u32>,
 
+    // Bit-banged Microwire EEPROM access through EECD (SK/CS/DI/DO).
+    microwire: MicrowireState,
+    // Host-controlled cable state (see `set_link_up`). Survives device reset.
+    link_up: bool,
+
     rx_pending: VecDeque<Vec<u8>>,
     tx_out: VecDeque<Vec<u8>>,
 
@@ -714,6 +772,8 @@ impl E1000Device {
             eeprom: [0xFFFF; 64],
             phy: [0; 32],
             other_regs: HashMap::new(),
+            microwire: MicrowireState::default(),
+            link_up: true,
             rx_pending: VecDeque::new(),
             tx_out: VecDeque::new(),
             tx_needs_poll: false,
@@ -724,29 +784,100 @@ impl E1000Device {
         dev
     }
 
+    /// Rebuild the NVM image: MAC address in words 0..2, 82540EM-style defaults elsewhere and a
+    /// checksum word so the image sums to [`EEPROM_SUM`].
+    ///
+    /// The defaults follow a typical 82540EM EEPROM (compatibility/init-control words, PCI IDs)
+    /// closely enough for drivers that validate the image before trusting it.
     fn init_eeprom_from_mac(&mut self) {
+        const TEMPLATE: [u16; 64] = [
+            0x0000, 0x0000, 0x0000, 0x0000, 0xFFFF, 0x0000, 0x0000, 0x0000, 0x3000, 0x1000, 0x6403,
+            0x0000, 0x0000, 0x0000, 0x0000, 0x3040, 0x0008, 0x2000, 0x7E14, 0x0048, 0x1000, 0x00D8,
+            0x0000, 0x2700, 0x6CC9, 0x3150, 0x0722, 0x040B, 0x0984, 0x0000, 0xC000, 0x0706, 0x1008,
+            0x0000, 0x0F04, 0x7FFF, 0x4D01, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+            0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x0100, 0x4000, 0x121C, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF,
+            0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x0000,
+        ];
+
+        self.eeprom = TEMPLATE;
         self.eeprom[0] = u16::from_le_bytes([self.mac_addr[0], self.mac_addr[1]]);
         self.eeprom[1] = u16::from_le_bytes([self.mac_addr[2], self.mac_addr[3]]);
         self.eeprom[2] = u16::from_le_bytes([self.mac_addr[4], self.mac_addr[5]]);
+        // Subsystem ID / subsystem vendor / device ID / vendor ID, mirroring PCI config space.
+        self.eeprom[0x0B] = PciConfig::DEVICE_ID;
+        self.eeprom[0x0C] = PciConfig::VENDOR_ID;
+        self.eeprom[0x0D] = PciConfig::DEVICE_ID;
+        self.eeprom[0x0E] = PciConfig::VENDOR_ID;
+
+        let sum = self.eeprom[..EEPROM_CHECKSUM_WORD]
+            .iter()
+            .fold(0u16, |acc, &w| acc.wrapping_add(w));
+        self.eeprom[EEPROM_CHECKSUM_WORD] = EEPROM_SUM.wrapping_sub(sum);
     }
 
     fn init_phy(&mut self) {
-        // Minimal PHY register set to keep common drivers happy.
-        //
-        // Registers are standard MII:
-        //  - 0: BMCR
-        //  - 1: BMSR
-        //  - 2/3: PHY ID
-        const MII_BMSR: usize = 1;
-        const MII_PHYSID1: usize = 2;
-        const MII_PHYSID2: usize = 3;
+        // Marvell 88E1011-style PHY, the part paired with the 82540EM. Drivers identify it via the
+        // ID registers and read the resolved speed/duplex from the PHY Specific Status register.
+        self.phy = [0; 32];
+        self.phy[PHY_BMCR] = 0x1140; // AN enable, full duplex, 1000Mb/s
+        self.phy[PHY_BMSR] = 0x7949; // 10/100 capabilities, extended status, AN ability
+        self.phy[PHY_ID1] = 0x0141;
+        self.phy[PHY_ID2] = 0x0C20;
+        self.phy[PHY_ANAR] = 0x0DE1;
+        self.phy[PHY_1000T_CTRL] = 0x0E00;
+        self.phy[PHY_EXT_STATUS] = 0x3000; // 1000BASE-T full/half duplex capable
+        self.phy[PHY_M88_SPEC_CTRL] = 0x0068;
+        self.apply_phy_link_state();
+    }
+
+    /// Update the link-dependent PHY status bits to match `link_up`.
+    fn apply_phy_link_state(&mut self) {
+        let bmsr_link = PHY_BMSR_LINK | PHY_BMSR_AN_COMPLETE;
+        if self.link_up {
+            self.phy[PHY_BMSR] |= bmsr_link;
+            self.phy[PHY_ANLPAR] = 0x45E1;
+            self.phy[PHY_1000T_STATUS] = 0x3C00;
+            self.phy[PHY_M88_SPEC_STATUS] = PHY_M88_STATUS_LINK_1000FD;
+        } else {
+            self.phy[PHY_BMSR] &= !bmsr_link;
+            self.phy[PHY_ANLPAR] = 0;
+            self.phy[PHY_1000T_STATUS] = 0;
+            self.phy[PHY_M88_SPEC_STATUS] = 0;
+        }
+    }
 
-        // BMSR: link up + auto-negotiation complete.
-        self.phy[MII_BMSR] = 0x0004 | 0x0020;
+    /// Host → device cable state. Updates `STATUS.LU` and the PHY link bits, and raises a Link
+    /// Status Change interrupt (`ICR.LSC`) when the state actually changes.
+    ///
+    /// The link state is host configuration: it survives device reset (`CTRL.RST`) and snapshot
+    /// restore.
+    pub fn set_link_up(&mut self, up: bool) {
+        let changed = self.link_up != up;
+        self.link_up = up;
+        self.apply_link_state();
+        if changed {
+            self.icr |= ICR_LSC;
+            self.update_irq_level();
+        }
+    }
 
-        // A plausible Intel-ish PHY ID (not intended to match real silicon).
-        self.phy[MII_PHYSID1] = 0x0141;
-        self.phy[MII_PHYSID2] = 0x0CC2;
+    /// Returns the cable state set by [`E1000Device::set_link_up`] (defaults to up).
+    pub fn link_up(&self) -> bool {
+        self.link_up
+    }
+
+    fn apply_link_state(&mut self) {
+        if self.link_up {
+            self.status |= STATUS_LU;
+        } else {
+            self.status &= !STATUS_LU;
+        }
+        self.apply_phy_link_state();
+    }
+
+    /// Returns a copy of the NVM image served through EERD and the EECD Microwire interface.
+    pub fn eeprom(&self) -> [u16; 64] {
+        self.eeprom
     }
 
     pub fn mac_addr(&self) -> [u8; 6] {
@@ -928,6 +1059,21 @@ impl E1000Device {
         }
     }
 
+    /// Whether [`poll`] has descriptor work to do or frames are queued in either direction.
+    ///
+    /// When this returns `false` and no host frames are waiting to be enqueued, [`poll`] and
+    /// [`pop_tx_frame`] are no-ops, so integrations can skip pumping an idle NIC.
+    ///
+    /// [`poll`]: E1000Device::poll
+    /// [`pop_tx_frame`]: E1000Device::pop_tx_frame
+    pub fn has_pending_work(&self) -> bool {
+        self.tx_needs_poll
+            || self.tx_work_pending()
+            || self.rx_needs_flush
+            || !self.rx_pending.is_empty()
+            || !self.tx_out.is_empty()
+    }
+
     /// Queue a host→guest Ethernet frame for later delivery.
     ///
     /// The caller is expected to invoke [`poll`] (or [`receive_frame`]) to flush
@@ -962,6 +1108,7 @@ impl E1000Device {
     fn reset(&mut self) {
         self.ctrl = 0;
         self.eecd = EECD_EE_PRES;
+        self.microwire = MicrowireState::default();
         self.eerd = 0;
         self.ctrl_ext = 0;
         self.mdic = MDIC_READY;
@@ -997,6 +1144,7 @@ impl E1000Device {
         self.ra_valid = true;
         self.init_eeprom_from_mac();
         self.init_phy();
+        self.apply_link_state();
 
         self.tx_needs_poll = false;
         self.rx_needs_flush = false;
@@ -1006,6 +1154,69 @@ impl E1000Device {
         self.irq_level = (self.icr & self.ims) != 0;
     }
 
+    fn eecd_read(&self) -> u32 {
+        let mut v = self.eecd | EECD_EE_PRES;
+        if (self.eecd & EECD_EE_REQ) != 0 {
+            v |= EECD_EE_GNT;
+        }
+        // DO idles high; during a READ it carries the addressed bit.
+        let mw = &self.microwire;
+        let word = self.eeprom[((mw.bit_out >> 4) as usize) % self.eeprom.len()];
+        if !mw.reading || (word >> (15 - (mw.bit_out & 0xF))) & 1 != 0 {
+            v |= EECD_DO;
+        }
+        v
+    }
+
+    fn eecd_write(&mut self, value: u32) {
+        let old = self.eecd;
+        self.eecd = (value & !(EECD_DO | EECD_EE_GNT)) | EECD_EE_PRES;
+
+        if (value & EECD_CS) == 0 {
+            return;
+        }
+        if (old & EECD_CS) == 0 {
+            // CS rising edge starts a new command.
+            self.microwire = MicrowireState::default();
+        }
+        if ((old ^ value) & EECD_SK) == 0 {
+            return;
+        }
+
+        let mw = &mut self.microwire;
+        if (value & EECD_SK) == 0 {
+            // Falling edge: advance the output bit.
+            mw.bit_out = mw.bit_out.wrapping_add(1);
+            return;
+        }
+
+        mw.shift_in = (mw.shift_in << 1) | u32::from((value & EECD_DI) != 0);
+        mw.bits_in = mw.bits_in.saturating_add(1);
+        if mw.bits_in == MICROWIRE_CMD_BITS && !mw.reading {
+            // The first falling edge after the address moves DO onto the word's MSB.
+            mw.bit_out = ((mw.shift_in & 0x3F) << 4).wrapping_sub(1);
+            mw.reading = ((mw.shift_in >> 6) & 0b111) == MICROWIRE_OPCODE_READ;
+        }
+    }
+
+    fn phy_write(&mut self, reg: usize, data: u16) {
+        match reg {
+            // Status/ID registers are read-only.
+            PHY_BMSR | PHY_ID1 | PHY_ID2 | PHY_ANLPAR | PHY_1000T_STATUS | PHY_EXT_STATUS
+            | PHY_M88_SPEC_STATUS => {}
+            PHY_BMCR => {
+                // Reset and restart-autonegotiation self-clear: negotiation completes instantly.
+                self.phy[PHY_BMCR] = data & !(PHY_BMCR_RESET | PHY_BMCR_RESTART_AN);
+                self.apply_phy_link_state();
+            }
+            _ => {
+                if let Some(slot) = self.phy.get_mut(reg) {
+                    *slot = data;
+                }
+            }
+        }
+    }
+
     fn tx_work_pending(&self) -> bool {
         match self.tx_ring_desc_count() {
             Some(desc_count) if desc_count != 0 => self.tdh % desc_count != self.tdt % desc_count,
@@ -1017,7 +1228,7 @@ impl E1000Device {
         match offset {
             REG_CTRL => self.ctrl,
             REG_STATUS => self.status,
-            REG_EECD => self.eecd,
+            REG_EECD => self.eecd_read(),
             REG_EERD => self.eerd,
             REG_CTRL_EXT => self.ctrl_ext,
             REG_MDIC => self.mdic,
@@ -1080,7 +1291,7 @@ impl E1000Device {
                     self.ctrl = value;
                 }
             }
-            REG_EECD => self.eecd = value | EECD_EE_PRES,
+            REG_EECD => self.eecd_write(value),
             REG_EERD => {
                 self.eerd = value;
                 if (value & EERD_START) != 0 {
@@ -1103,9 +1314,7 @@ impl E1000Device {
                     let v = self.phy.get(reg).copied().unwrap_or(0) as u32;
                     self.mdic = (value & (MDIC_REG_MASK | MDIC_PHY_MASK)) | MDIC_READY | v;
                 } else if (value & MDIC_OP_WRITE) != 0 {
-                    if let Some(slot) = self.phy.get_mut(reg) {
-                        *slot = data;
-                    }
+                    self.phy_write(reg, data);
                     self.mdic =
                         (value & (MDIC_REG_MASK | MDIC_PHY_MASK)) | MDIC_READY | data as u32;
                 } else {
@@ -1701,7 +1910,8 @@ impl E1000Device {
         // Restore MMIO-visible register state + internal runtime state.
         self.ctrl = state.ctrl;
         self.status = state.status;
-        self.eecd = state.eecd | EECD_EE_PRES;
+        self.eecd = (state.eecd & !(EECD_DO | EECD_EE_GNT)) | EECD_EE_PRES;
+        self.microwire = MicrowireState::default();
         self.eerd = state.eerd;
         self.ctrl_ext = state.ctrl_ext;
         self.mdic = state.mdic | MDIC_READY;
@@ -1760,9 +1970,14 @@ impl E1000Device {
         self.mac_addr = state.mac_addr;
         self.ra_valid = state.ra_valid;
         self.eeprom = state.eeprom;
-        // Keep EEPROM words 0..2 coherent with `mac_addr` as enforced by the live device model.
-        self.init_eeprom_from_mac();
         self.phy = state.phy;
+        // The cable state belongs to the host, not the snapshot. If it differs from what the guest
+        // last observed, update STATUS/PHY and report it as a link change.
+        let snapshot_link_up = (self.status & STATUS_LU) != 0;
+        if snapshot_link_up != self.link_up {
+            self.apply_link_state();
+            self.icr |= ICR_LSC;
+        }
 
         self.other_regs.clear();
         for (k, v) in &state.other_regs {
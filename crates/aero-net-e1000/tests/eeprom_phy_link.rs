@@ -0,0 +1,197 @@
+use aero_net_e1000::{E1000Device, EEPROM_CHECKSUM_WORD, EEPROM_SUM, ICR_LSC};
+
+const REG_STATUS: u32 = 0x0008;
+const REG_EECD: u32 = 0x0010;
+const REG_EERD: u32 = 0x0014;
+const REG_MDIC: u32 = 0x0020;
+const REG_ICR: u32 = 0x00C0;
+const REG_IMS: u32 = 0x00D0;
+
+const EECD_SK: u32 = 1 << 0;
+const EECD_CS: u32 = 1 << 1;
+const EECD_DI: u32 = 1 << 2;
+const EECD_DO: u32 = 1 << 3;
+const EECD_EE_REQ: u32 = 1 << 6;
+const EECD_EE_GNT: u32 = 1 << 7;
+const EECD_EE_PRES: u32 = 1 << 8;
+
+const STATUS_LU: u32 = 1 << 1;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+
+fn eerd_read(dev: &mut E1000Device, addr: u16) -> u16 {
+    dev.mmio_write_u32_reg(REG_EERD, (u32::from(addr) << 8) | 1);
+    let eerd = dev.mmio_read_u32(REG_EERD);
+    assert_ne!(eerd & (1 << 4), 0, "EERD.DONE");
+    (eerd >> 16) as u16
+}
+
+/// Read one word through the bit-banged Microwire interface, the way the Linux/Windows e1000
+/// drivers do on the 82540: request the bus, raise CS, shift out `110` + 6-bit address, then shift
+/// in 16 data bits.
+fn microwire_read(dev: &mut E1000Device, addr: u16) -> u16 {
+    let mut eecd = dev.mmio_read_u32(REG_EECD) | EECD_EE_REQ;
+    dev.mmio_write_u32_reg(REG_EECD, eecd);
+    assert_ne!(dev.mmio_read_u32(REG_EECD) & EECD_EE_GNT, 0);
+
+    eecd &= !(EECD_SK | EECD_DI);
+    eecd |= EECD_CS;
+    dev.mmio_write_u32_reg(REG_EECD, eecd);
+
+    let cmd = (0b110u32 << 6) | u32::from(addr & 0x3F);
+    for bit in (0..9).rev() {
+        if (cmd >> bit) & 1 != 0 {
+            eecd |= EECD_DI;
+        } else {
+            eecd &= !EECD_DI;
+        }
+        dev.mmio_write_u32_reg(REG_EECD, eecd);
+        dev.mmio_write_u32_reg(REG_EECD, eecd | EECD_SK);
+        dev.mmio_write_u32_reg(REG_EECD, eecd);
+    }
+    eecd &= !EECD_DI;
+    dev.mmio_write_u32_reg(REG_EECD, eecd);
+
+    let mut word = 0u16;
+    for _ in 0..16 {
+        dev.mmio_write_u32_reg(REG_EECD, eecd | EECD_SK);
+        let v = dev.mmio_read_u32(REG_EECD);
+        word = (word << 1) | u16::from((v & EECD_DO) != 0);
+        dev.mmio_write_u32_reg(REG_EECD, eecd);
+    }
+
+    eecd &= !(EECD_CS | EECD_EE_REQ);
+    dev.mmio_write_u32_reg(REG_EECD, eecd);
+    word
+}
+
+fn phy_read(dev: &mut E1000Device, reg: u32) -> u16 {
+    dev.mmio_write_u32_reg(REG_MDIC, 0x0800_0000 | (1 << 21) | (reg << 16));
+    let mdic = dev.mmio_read_u32(REG_MDIC);
+    assert_ne!(mdic & 0x1000_0000, 0, "MDIC.READY");
+    mdic as u16
+}
+
+fn phy_write(dev: &mut E1000Device, reg: u32, data: u16) {
+    dev.mmio_write_u32_reg(
+        REG_MDIC,
+        0x0400_0000 | (1 << 21) | (reg << 16) | u32::from(data),
+    );
+}
+
+#[test]
+fn eerd_and_microwire_return_the_same_checksummed_image() {
+    let mut dev = E1000Device::new(MAC);
+    assert_ne!(dev.mmio_read_u32(REG_EECD) & EECD_EE_PRES, 0);
+
+    let via_eerd: Vec<u16> = (0..64).map(|addr| eerd_read(&mut dev, addr)).collect();
+    let via_microwire: Vec<u16> = (0..64).map(|addr| microwire_read(&mut dev, addr)).collect();
+    assert_eq!(via_eerd, via_microwire);
+    assert_eq!(via_eerd, dev.eeprom().to_vec());
+
+    assert_eq!(via_eerd[0], u16::from_le_bytes([MAC[0], MAC[1]]));
+    assert_eq!(via_eerd[1], u16::from_le_bytes([MAC[2], MAC[3]]));
+    assert_eq!(via_eerd[2], u16::from_le_bytes([MAC[4], MAC[5]]));
+
+    let sum = via_eerd[..=EEPROM_CHECKSUM_WORD]
+        .iter()
+        .fold(0u16, |acc, &w| acc.wrapping_add(w));
+    assert_eq!(sum, EEPROM_SUM);
+}
+
+#[test]
+fn checksum_tracks_mac_reprogramming() {
+    let mut dev = E1000Device::new(MAC);
+    // RAL0/RAH0 writes update the MAC (and therefore the NVM image).
+    dev.mmio_write_u32_reg(0x5400, 0x4433_2211);
+    dev.mmio_write_u32_reg(0x5404, 0x8000_6655);
+
+    let image = dev.eeprom();
+    assert_eq!(image[0], 0x2211);
+    assert_eq!(image[2], 0x6655);
+    let sum = image.iter().fold(0u16, |acc, &w| acc.wrapping_add(w));
+    assert_eq!(sum, EEPROM_SUM);
+}
+
+#[test]
+fn microwire_ignores_non_read_opcodes() {
+    let mut dev = E1000Device::new(MAC);
+    let mut eecd = EECD_CS;
+    dev.mmio_write_u32_reg(REG_EECD, eecd);
+    // EWEN-style opcode (start + 00) followed by address bits: DO stays idle-high.
+    for bit in (0..9).rev() {
+        let di = ((0b1_0011_0000u32 >> bit) & 1) != 0;
+        eecd = if di { eecd | EECD_DI } else { eecd & !EECD_DI };
+        dev.mmio_write_u32_reg(REG_EECD, eecd | EECD_SK);
+        dev.mmio_write_u32_reg(REG_EECD, eecd);
+    }
+    for _ in 0..16 {
+        dev.mmio_write_u32_reg(REG_EECD, eecd | EECD_SK);
+        assert_ne!(dev.mmio_read_u32(REG_EECD) & EECD_DO, 0);
+        dev.mmio_write_u32_reg(REG_EECD, eecd);
+    }
+}
+
+#[test]
+fn phy_reports_marvell_ids_and_resolved_gigabit_link() {
+    let mut dev = E1000Device::new(MAC);
+    assert_eq!(phy_read(&mut dev, 2), 0x0141);
+    assert_eq!(phy_read(&mut dev, 3), 0x0C20);
+
+    let bmsr = phy_read(&mut dev, 1);
+    assert_eq!(bmsr & 0x0024, 0x0024, "link + AN complete: {bmsr:#06x}");
+    assert_ne!(phy_read(&mut dev, 4), 0, "ANAR");
+    assert_ne!(phy_read(&mut dev, 5), 0, "ANLPAR");
+    assert_eq!(
+        phy_read(&mut dev, 10) & 0x0C00,
+        0x0C00,
+        "LP 1000BASE-T FD/HD"
+    );
+    assert_eq!(phy_read(&mut dev, 17), 0xAC00, "1000/FD/resolved/link");
+
+    // BMCR reset and restart-AN self-clear; read-only registers ignore writes.
+    phy_write(&mut dev, 0, 0x8000 | 0x1000 | 0x0200);
+    assert_eq!(phy_read(&mut dev, 0), 0x1000);
+    phy_write(&mut dev, 1, 0);
+    assert_eq!(phy_read(&mut dev, 1), bmsr);
+    phy_write(&mut dev, 2, 0xFFFF);
+    assert_eq!(phy_read(&mut dev, 2), 0x0141);
+}
+
+#[test]
+fn link_flap_raises_lsc_and_updates_status_and_phy() {
+    let mut dev = E1000Device::new(MAC);
+    dev.mmio_write_u32_reg(REG_IMS, ICR_LSC);
+    assert!(dev.link_up());
+    assert_ne!(dev.mmio_read_u32(REG_STATUS) & STATUS_LU, 0);
+
+    // Setting the current state again is not a change.
+    dev.set_link_up(true);
+    assert!(!dev.irq_level());
+
+    dev.set_link_up(false);
+    assert!(dev.irq_level());
+    assert_eq!(dev.mmio_read_u32(REG_STATUS) & STATUS_LU, 0);
+    assert_eq!(phy_read(&mut dev, 1) & 0x0024, 0);
+    assert_eq!(phy_read(&mut dev, 17), 0);
+    assert_eq!(dev.mmio_read_u32(REG_ICR) & ICR_LSC, ICR_LSC);
+    assert!(!dev.irq_level());
+
+    dev.set_link_up(true);
+    assert!(dev.irq_level());
+    assert_ne!(dev.mmio_read_u32(REG_STATUS) & STATUS_LU, 0);
+    assert_eq!(phy_read(&mut dev, 1) & 0x0024, 0x0024);
+    assert_eq!(phy_read(&mut dev, 17), 0xAC00);
+    assert_eq!(dev.mmio_read_u32(REG_ICR) & ICR_LSC, ICR_LSC);
+    assert!(!dev.irq_level());
+}
+
+#[test]
+fn link_state_survives_device_reset() {
+    let mut dev = E1000Device::new(MAC);
+    dev.set_link_up(false);
+    dev.mmio_write_u32_reg(0x0000, 1 << 26); // CTRL.RST
+    assert!(!dev.link_up());
+    assert_eq!(dev.mmio_read_u32(REG_STATUS) & STATUS_LU, 0);
+    assert_eq!(phy_read(&mut dev, 1) & 0x0004, 0);
+}
@@ -324,6 +324,7 @@ impl<B: NetworkBackend> E1000Pump<B> {
 pub struct VirtioNetBackendAdapter {
     backend: Option<Box<dyn NetworkBackend>>,
     rx_budget: usize,
+    frames: u64,
 }
 
 impl VirtioNetBackendAdapter {
@@ -331,6 +332,7 @@ impl VirtioNetBackendAdapter {
         Self {
             backend,
             rx_budget: 0,
+            frames: 0,
         }
     }
 
@@ -345,10 +347,18 @@ impl VirtioNetBackendAdapter {
     pub fn set_rx_budget(&mut self, budget: usize) {
         self.rx_budget = budget;
     }
+
+    /// Total frames passed through the adapter (guest TX plus delivered host RX).
+    ///
+    /// Frames transmitted while no backend is attached are counted too: the guest still sent them.
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
 }
 
 impl NetworkBackend for VirtioNetBackendAdapter {
     fn transmit(&mut self, frame: Vec<u8>) {
+        self.frames = self.frames.wrapping_add(1);
         if let Some(backend) = self.backend.as_mut() {
             backend.transmit(frame);
         }
@@ -364,6 +374,7 @@ impl NetworkBackend for VirtioNetBackendAdapter {
             .and_then(|backend| backend.poll_receive());
         if frame.is_some() {
             self.rx_budget = self.rx_budget.saturating_sub(1);
+            self.frames = self.frames.wrapping_add(1);
         }
         frame
     }
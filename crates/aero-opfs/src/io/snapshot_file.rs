@@ -20,9 +20,15 @@ fn disk_error_to_io(err: crate::DiskError) -> io::Error {
         | DiskError::OffsetOverflow => io::Error::new(io::ErrorKind::InvalidInput, err),
         DiskError::CorruptImage(_)
         | DiskError::InvalidSparseHeader(_)
-        | DiskError::CorruptSparseImage(_) => io::Error::new(io::ErrorKind::InvalidData, err),
+        | DiskError::CorruptSparseImage(_)
+        | DiskError::ChecksumMismatch { .. } => io::Error::new(io::ErrorKind::InvalidData, err),
         DiskError::InvalidConfig(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
         DiskError::Io(_) => io::Error::other(err),
+        DiskError::NativeIo { ref source, .. } => io::Error::new(source.kind(), err),
+        DiskError::Context { .. } => {
+            let kind = disk_error_to_io(err.root().clone()).kind();
+            io::Error::new(kind, err)
+        }
     }
 }
 
@@ -563,6 +569,14 @@ mod tests {
             ErrorKind::InvalidData,
         );
         assert_kind(DiskError::CorruptSparseImage("bad"), ErrorKind::InvalidData);
+        assert_kind(
+            DiskError::ChecksumMismatch {
+                block_idx: 1,
+                expected: 0,
+                actual: 1,
+            },
+            ErrorKind::InvalidData,
+        );
         assert_kind(DiskError::Io("boom".to_string()), ErrorKind::Other);
     }
 
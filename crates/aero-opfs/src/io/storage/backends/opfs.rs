@@ -1506,6 +1506,7 @@ mod wasm {
                 AeroSparseConfig {
                     disk_size_bytes: 1024 * 1024,
                     block_size_bytes: 32 * 1024,
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -589,6 +589,10 @@ impl PortIoDevice for PcIdePort {
         self.sync_config();
         self.ide.borrow_mut().io_write(port, size, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "piix3-ide"
+    }
 }
 
 /// Bus Master IDE (BAR4) handler registered with the platform's [`PciIoBarRouter`].
@@ -816,6 +820,10 @@ impl PortIoDevice for PciIoBarRouterPort {
             .borrow_mut()
             .dispatch_write(port, size_usize, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "pci-io-bar-window"
+    }
 }
 
 #[cfg(feature = "hda")]
@@ -826,11 +834,11 @@ struct HdaDmaMemory<'a> {
 #[cfg(feature = "hda")]
 impl aero_audio::mem::MemoryAccess for HdaDmaMemory<'_> {
     fn read_physical(&self, addr: u64, buf: &mut [u8]) {
-        self.mem.borrow_mut().read_physical(addr, buf);
+        memory::MemoryBus::read_physical(&mut self.mem.borrow_mut().bus_master(), addr, buf);
     }
 
     fn write_physical(&mut self, addr: u64, buf: &[u8]) {
-        self.mem.borrow_mut().write_physical(addr, buf);
+        memory::MemoryBus::write_physical(&mut self.mem.borrow_mut().bus_master(), addr, buf);
     }
 }
 
@@ -2742,7 +2750,7 @@ impl PcPlatform {
                 sync_msix_capability_into_config(cfg, enabled, function_masked);
             }
         }
-        nvme.process(&mut self.memory);
+        nvme.process(&mut self.memory.bus_master());
 
         // Mirror device-managed MSI pending bits back into the canonical PCI config space so guest
         // config reads observe them. The canonical config space cannot infer pending bits on its
@@ -2800,7 +2808,7 @@ impl PcPlatform {
         }
 
         if bus_master_enabled {
-            ahci.process(&mut self.memory);
+            ahci.process(&mut self.memory.bus_master());
         }
 
         // Mirror device-managed MSI pending bits back into the canonical PCI config space so guest
@@ -2842,7 +2850,7 @@ impl PcPlatform {
         }
 
         let mut ide = ide.borrow_mut();
-        ide.tick(&mut self.memory);
+        ide.tick(&mut self.memory.bus_master());
     }
 
     pub fn process_virtio_blk(&mut self) {
@@ -2938,7 +2946,7 @@ impl PcPlatform {
         }
 
         if bus_master_enabled {
-            dev.poll(&mut self.memory);
+            dev.poll(&mut self.memory.bus_master());
         }
     }
 
@@ -7,20 +7,26 @@ fn pc_platform_registers_dma8237_ports_and_resets_state() {
     // Unmapped ports float high; verify an adjacent port outside the DMA range behaves as open bus.
     assert_eq!(pc.io.read_u8(0x10), 0xFF);
 
-    // DMA controller ports should be registered and default to 0 (register file stub).
+    // DMA controller ports should be registered and default to 0.
     assert_eq!(pc.io.read_u8(0x00), 0);
     assert_eq!(pc.io.read_u8(0x08), 0);
     assert_eq!(pc.io.read_u8(0x80), 0);
     assert_eq!(pc.io.read_u8(0xC0), 0);
 
-    // Writes should be observable on subsequent reads, including multi-byte access sizes.
+    // Channel address registers are accessed low byte then high byte through the flip-flop.
+    pc.io.write_u8(0x0C, 0);
     pc.io.write_u8(0x00, 0x12);
-    pc.io.write_u8(0x01, 0x34);
+    pc.io.write_u8(0x00, 0x34);
     assert_eq!(pc.io.read_u8(0x00), 0x12);
-    assert_eq!(pc.io.read(0x00, 2) as u16, 0x3412);
+    assert_eq!(pc.io.read_u8(0x00), 0x34);
+
+    // Page registers are plain latches, including multi-byte access sizes.
+    pc.io.write(0x81, 2, 0x5678);
+    assert_eq!(pc.io.read(0x81, 2) as u16, 0x5678);
 
     // Platform reset should clear the DMA controller state for deterministic power-on behavior.
     pc.reset();
     assert_eq!(pc.io.read_u8(0x00), 0);
-    assert_eq!(pc.io.read(0x00, 2) as u16, 0);
+    assert_eq!(pc.io.read_u8(0x00), 0);
+    assert_eq!(pc.io.read(0x81, 2) as u16, 0);
 }
@@ -25,11 +25,11 @@ struct MemIso {
 }
 
 impl IsoBackend for MemIso {
-    fn sector_count(&self) -> u32 {
-        (self.bytes.len() / AtapiCdrom::SECTOR_SIZE) as u32
+    fn sector_count(&self) -> u64 {
+        (self.bytes.len() / AtapiCdrom::SECTOR_SIZE) as u64
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
         if !buf.len().is_multiple_of(AtapiCdrom::SECTOR_SIZE) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
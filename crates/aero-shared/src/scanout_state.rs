@@ -13,11 +13,28 @@
 //! - The writer sets [`SCANOUT_STATE_GENERATION_BUSY_BIT`] before writing fields.
 //! - The writer stores the new committed generation (busy bit cleared) as the last step.
 //! - Readers spin/retry if the busy bit is set or if the generation changes mid-snapshot.
+//!
+//! ## Frame slots
+//!
+//! With [`SCANOUT_SOURCE_FRAME_SLOTS`] the scanout pixels are not read from guest memory but from
+//! a host-provided region holding two RGBA8 framebuffer slots (see [`ScanoutFrameSlots`]). The
+//! producer always writes the slot that is *not* the current front, then publishes the new
+//! descriptor together with the flipped front index/frame sequence ([`header_index::FRONT`],
+//! stored with release ordering). Consumers copy the front slot and re-check `generation`
+//! afterwards; any flip during the copy makes them retry, so they never observe a half-written
+//! frame.
+//!
+//! When the region is too small to hold two slots of the current frame size, both slot offsets
+//! are equal and the producer writes in place (the historical single-buffer behavior); this is
+//! reported by clearing [`SCANOUT_STATE_FLAG_DOUBLE_BUFFERED`].
+
+use core::fmt;
+use core::ptr::NonNull;
 
 #[cfg(all(feature = "loom", test))]
-use loom::sync::atomic::AtomicU32;
+use loom::sync::atomic::{fence, AtomicU32};
 #[cfg(not(all(feature = "loom", test)))]
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{fence, AtomicU32};
 
 use std::sync::atomic::Ordering;
 
@@ -26,6 +43,9 @@ use aero_protocol::aerogpu::aerogpu_pci::AerogpuFormat;
 pub const SCANOUT_SOURCE_LEGACY_TEXT: u32 = 0;
 pub const SCANOUT_SOURCE_LEGACY_VBE_LFB: u32 = 1;
 pub const SCANOUT_SOURCE_WDDM: u32 = 2;
+/// Pixels are host-composed RGBA8 frames stored in the frame slots (see [`ScanoutFrameSlots`]);
+/// `base_paddr_*` are unused.
+pub const SCANOUT_SOURCE_FRAME_SLOTS: u32 = 3;
 
 /// Scanout format values use the AeroGPU `AerogpuFormat` (`u32`) discriminants.
 ///
@@ -59,9 +79,19 @@ pub const SCANOUT_FORMAT_R8G8B8X8_SRGB: u32 = AerogpuFormat::R8G8B8X8UnormSrgb a
 /// this bit set and increments by 1 per completed update.
 pub const SCANOUT_STATE_GENERATION_BUSY_BIT: u32 = 1 << 31;
 
+/// Set in [`header_index::FLAGS`] when the published frame slots are distinct, i.e. the producer
+/// never writes into the slot a consumer may be reading.
+pub const SCANOUT_STATE_FLAG_DOUBLE_BUFFERED: u32 = 1 << 0;
+
+/// Double-buffered: slot 0 and slot 1.
+pub const SCANOUT_FRAME_SLOTS: usize = 2;
+
+/// Slot offsets are aligned to this to keep pixel rows aligned for host-side copies.
+pub const SCANOUT_FRAME_SLOT_ALIGNMENT: usize = 64;
+
 /// The scanout state is an array of 32-bit words to keep it trivially shareable
 /// with JS as an `Int32Array`.
-pub const SCANOUT_STATE_U32_LEN: usize = 8;
+pub const SCANOUT_STATE_U32_LEN: usize = 12;
 pub const SCANOUT_STATE_BYTE_LEN: usize = SCANOUT_STATE_U32_LEN * 4;
 
 pub mod header_index {
@@ -75,6 +105,11 @@ pub mod header_index {
     pub const HEIGHT: usize = 5;
     pub const PITCH_BYTES: usize = 6;
     pub const FORMAT: usize = 7;
+    pub const FLAGS: usize = 8;
+    /// Packed `frame_seq << 1 | front_index`.
+    pub const FRONT: usize = 9;
+    pub const SLOT0_OFFSET: usize = 10;
+    pub const SLOT1_OFFSET: usize = 11;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -87,6 +122,10 @@ pub struct ScanoutStateUpdate {
     pub pitch_bytes: u32,
     /// Pixel format stored as an AeroGPU `AerogpuFormat` (`u32`) discriminant.
     pub format: u32,
+    /// Byte offsets of the frame slots within the frame region ([`SCANOUT_SOURCE_FRAME_SLOTS`]
+    /// only; 0 otherwise). Equal offsets mean the frame is single-buffered.
+    pub slot0_offset: u32,
+    pub slot1_offset: u32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -100,12 +139,32 @@ pub struct ScanoutStateSnapshot {
     pub pitch_bytes: u32,
     /// Pixel format stored as an AeroGPU `AerogpuFormat` (`u32`) discriminant.
     pub format: u32,
+    pub flags: u32,
+    /// Front slot index (0 or 1) of the last published frame.
+    pub front_index: u32,
+    /// 31-bit wrapping count of published frames.
+    pub frame_seq: u32,
+    pub slot0_offset: u32,
+    pub slot1_offset: u32,
 }
 
 impl ScanoutStateSnapshot {
     pub fn base_paddr(self) -> u64 {
         (self.base_paddr_hi as u64) << 32 | self.base_paddr_lo as u64
     }
+
+    pub fn is_double_buffered(self) -> bool {
+        self.flags & SCANOUT_STATE_FLAG_DOUBLE_BUFFERED != 0
+    }
+
+    /// Byte offset (within the frame region) of the slot holding the front frame.
+    pub fn front_slot_offset(self) -> u32 {
+        if self.front_index & 1 == 0 {
+            self.slot0_offset
+        } else {
+            self.slot1_offset
+        }
+    }
 }
 
 #[repr(C)]
@@ -124,6 +183,13 @@ pub struct ScanoutState {
     pub pitch_bytes: AtomicU32,
     /// Pixel format stored as an AeroGPU `AerogpuFormat` (`u32`) discriminant.
     pub format: AtomicU32,
+    /// Capability bits (`SCANOUT_STATE_FLAG_*`), derived from the published slot offsets.
+    pub flags: AtomicU32,
+    /// Packed `frame_seq << 1 | front_index`, stored with release ordering when a frame is
+    /// flipped.
+    pub front: AtomicU32,
+    pub slot0_offset: AtomicU32,
+    pub slot1_offset: AtomicU32,
 }
 
 impl ScanoutState {
@@ -137,6 +203,10 @@ impl ScanoutState {
             height: AtomicU32::new(0),
             pitch_bytes: AtomicU32::new(0),
             format: AtomicU32::new(SCANOUT_FORMAT_B8G8R8X8),
+            flags: AtomicU32::new(0),
+            front: AtomicU32::new(0),
+            slot0_offset: AtomicU32::new(0),
+            slot1_offset: AtomicU32::new(0),
         }
     }
 
@@ -165,6 +235,10 @@ impl ScanoutState {
             let height = self.height.load(Ordering::SeqCst);
             let pitch_bytes = self.pitch_bytes.load(Ordering::SeqCst);
             let format = self.format.load(Ordering::SeqCst);
+            let flags = self.flags.load(Ordering::SeqCst);
+            let front = self.front.load(Ordering::Acquire);
+            let slot0_offset = self.slot0_offset.load(Ordering::SeqCst);
+            let slot1_offset = self.slot1_offset.load(Ordering::SeqCst);
 
             let gen1 = self.generation.load(Ordering::SeqCst);
             if gen0 != gen1 {
@@ -181,6 +255,11 @@ impl ScanoutState {
                 height,
                 pitch_bytes,
                 format,
+                flags,
+                front_index: front & 1,
+                frame_seq: front >> 1,
+                slot0_offset,
+                slot1_offset,
             });
         }
 
@@ -204,6 +283,29 @@ impl ScanoutState {
     ///
     /// Returns `None` when the write lock cannot be acquired (e.g. busy bit wedged).
     pub fn try_publish(&self, update: ScanoutStateUpdate) -> Option<u32> {
+        self.try_publish_inner(update, None)
+    }
+
+    /// Best-effort publish of a frame-slot update that also flips the front slot to
+    /// `front_index` and advances the frame sequence number.
+    ///
+    /// The caller must have finished writing the pixels of slot `front_index` before calling
+    /// this; the descriptor and the new front are made visible together.
+    pub fn try_publish_frame(&self, update: ScanoutStateUpdate, front_index: u32) -> Option<u32> {
+        self.try_publish_inner(update, Some(front_index & 1))
+    }
+
+    /// Current front slot index and frame sequence number (acquire load).
+    pub fn front(&self) -> (u32, u32) {
+        let front = self.front.load(Ordering::Acquire);
+        (front & 1, front >> 1)
+    }
+
+    fn try_publish_inner(
+        &self,
+        update: ScanoutStateUpdate,
+        front_index: Option<u32>,
+    ) -> Option<u32> {
         // Acquire the write lock by setting the busy bit.
         const MAX_SPINS: usize = 1_000_000;
         let mut start = self.generation.load(Ordering::SeqCst);
@@ -249,6 +351,23 @@ impl ScanoutState {
         self.pitch_bytes.store(update.pitch_bytes, Ordering::SeqCst);
         test_yield();
         self.format.store(update.format, Ordering::SeqCst);
+        test_yield();
+        let flags = if update.source == SCANOUT_SOURCE_FRAME_SLOTS
+            && update.slot0_offset != update.slot1_offset
+        {
+            SCANOUT_STATE_FLAG_DOUBLE_BUFFERED
+        } else {
+            0
+        };
+        self.flags.store(flags, Ordering::SeqCst);
+        self.slot0_offset
+            .store(update.slot0_offset, Ordering::SeqCst);
+        self.slot1_offset
+            .store(update.slot1_offset, Ordering::SeqCst);
+        if let Some(index) = front_index {
+            let seq = (self.front.load(Ordering::Relaxed) >> 1).wrapping_add(1);
+            self.front.store(seq << 1 | index, Ordering::Release);
+        }
 
         test_yield();
 
@@ -274,6 +393,197 @@ impl Default for ScanoutState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanoutFrameSlotsError {
+    NullBasePtr,
+    UnalignedBasePtr {
+        addr: usize,
+    },
+    /// Slot offsets are published as `u32`, so the region must be addressable with them.
+    RegionTooLarge {
+        len: usize,
+    },
+}
+
+impl fmt::Display for ScanoutFrameSlotsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanoutFrameSlotsError::NullBasePtr => {
+                write!(f, "scanout frame region base pointer is null")
+            }
+            ScanoutFrameSlotsError::UnalignedBasePtr { addr } => write!(
+                f,
+                "scanout frame region base pointer (0x{addr:x}) is not 4-byte aligned"
+            ),
+            ScanoutFrameSlotsError::RegionTooLarge { len } => write!(
+                f,
+                "scanout frame region ({len} bytes) exceeds the u32 slot offset range"
+            ),
+        }
+    }
+}
+
+/// A view over the shared region holding the [`SCANOUT_SOURCE_FRAME_SLOTS`] framebuffers.
+///
+/// Like [`crate::shared_framebuffer::SharedFramebuffer`], this uses raw pointers because the
+/// region is mutated concurrently by another thread/worker; safety relies on the producer only
+/// writing the back slot and consumers validating their copy against `generation`.
+///
+/// Slot offsets are relative to the start of the region. Slot 1 always starts at the middle of
+/// the region (rounded down to [`SCANOUT_FRAME_SLOT_ALIGNMENT`]), so the two slots never overlap
+/// regardless of the frame size.
+#[derive(Clone, Copy)]
+pub struct ScanoutFrameSlots {
+    base: NonNull<u8>,
+    len: usize,
+}
+
+// A handle to shared memory; the pointer does not imply ownership.
+unsafe impl Send for ScanoutFrameSlots {}
+unsafe impl Sync for ScanoutFrameSlots {}
+
+impl ScanoutFrameSlots {
+    /// # Safety
+    /// `base` must point to a region of at least `len` bytes that stays valid for as long as the
+    /// returned handle (or any copy of it) is used.
+    pub unsafe fn from_raw_parts(
+        base: *mut u8,
+        len: usize,
+    ) -> Result<Self, ScanoutFrameSlotsError> {
+        let Some(base) = NonNull::new(base) else {
+            return Err(ScanoutFrameSlotsError::NullBasePtr);
+        };
+        let addr = base.as_ptr() as usize;
+        if !addr.is_multiple_of(4) {
+            return Err(ScanoutFrameSlotsError::UnalignedBasePtr { addr });
+        }
+        if u32::try_from(len).is_err() {
+            return Err(ScanoutFrameSlotsError::RegionTooLarge { len });
+        }
+        Ok(Self { base, len })
+    }
+
+    pub fn byte_len(self) -> usize {
+        self.len
+    }
+
+    /// Slot offsets used for frames of `frame_bytes`.
+    ///
+    /// Returns distinct offsets when two slots fit, `[0, 0]` (single-buffered, written in place)
+    /// when only one does, and `None` when the frame does not fit at all.
+    pub fn slot_offsets(self, frame_bytes: usize) -> Option<[u32; SCANOUT_FRAME_SLOTS]> {
+        let half = (self.len / 2) & !(SCANOUT_FRAME_SLOT_ALIGNMENT - 1);
+        if half != 0 && frame_bytes <= half {
+            Some([0, half as u32])
+        } else if frame_bytes <= self.len {
+            Some([0, 0])
+        } else {
+            None
+        }
+    }
+
+    /// Write a tightly packed RGBA8 frame (pixels stored as `u32::from_le_bytes([r, g, b, a])`)
+    /// into the back slot and publish it as the new front.
+    ///
+    /// Returns the published generation, or `None` if the frame does not fit in the region or
+    /// the write lock cannot be acquired.
+    pub fn try_publish_frame(
+        self,
+        state: &ScanoutState,
+        width: u32,
+        height: u32,
+        pixels: &[u32],
+    ) -> Option<u32> {
+        debug_assert_eq!(pixels.len() as u64, u64::from(width) * u64::from(height));
+        let frame_bytes = pixels.len().checked_mul(4)?;
+        let offsets = self.slot_offsets(frame_bytes)?;
+
+        // Only this producer flips the front, so the back slot cannot change underneath us.
+        let (front, _) = state.front();
+        let back = front ^ 1;
+        // Safety: `slot_offsets` guarantees `offset + frame_bytes <= len`, and consumers never
+        // read the back slot of a double-buffered region.
+        unsafe {
+            let dst = self.base.as_ptr().add(offsets[back as usize] as usize);
+            #[cfg(target_endian = "little")]
+            core::ptr::copy_nonoverlapping(pixels.as_ptr() as *const u8, dst, frame_bytes);
+            #[cfg(not(target_endian = "little"))]
+            for (i, px) in pixels.iter().enumerate() {
+                core::ptr::copy_nonoverlapping(px.to_le_bytes().as_ptr(), dst.add(i * 4), 4);
+            }
+        }
+
+        state.try_publish_frame(
+            ScanoutStateUpdate {
+                source: SCANOUT_SOURCE_FRAME_SLOTS,
+                base_paddr_lo: 0,
+                base_paddr_hi: 0,
+                width,
+                height,
+                pitch_bytes: width.checked_mul(4)?,
+                format: SCANOUT_FORMAT_R8G8B8A8,
+                slot0_offset: offsets[0],
+                slot1_offset: offsets[1],
+            },
+            back,
+        )
+    }
+
+    /// Copy the current front frame into `out` and return the descriptor it was copied under.
+    ///
+    /// The copy is retried if the producer flips while it is in progress. Returns `None` if the
+    /// scanout is not a frame-slot scanout, the descriptor does not fit the region, or no stable
+    /// copy could be taken. Single-buffered frames are written in place and may still tear.
+    pub fn try_read_front(
+        self,
+        state: &ScanoutState,
+        out: &mut Vec<u8>,
+    ) -> Option<ScanoutStateSnapshot> {
+        const MAX_ATTEMPTS: usize = 1024;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let snap = state.try_snapshot()?;
+            if snap.source != SCANOUT_SOURCE_FRAME_SLOTS {
+                return None;
+            }
+            let bytes = (snap.pitch_bytes as usize).checked_mul(snap.height as usize)?;
+            let offset = snap.front_slot_offset() as usize;
+            if offset.checked_add(bytes)? > self.len {
+                return None;
+            }
+
+            out.clear();
+            out.reserve(bytes);
+            let pitch = (snap.pitch_bytes as usize).max(1);
+            let mut copied = 0;
+            while copied < bytes {
+                let n = pitch.min(bytes - copied);
+                // Safety: bounds checked above; `out` has capacity for `bytes`.
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        self.base.as_ptr().add(offset + copied),
+                        out.as_mut_ptr().add(copied),
+                        n,
+                    );
+                }
+                copied += n;
+                test_yield();
+            }
+            // Safety: all `bytes` bytes were initialized above.
+            unsafe { out.set_len(bytes) };
+
+            // Seqlock validation: the slot was stable iff no flip was published during the copy.
+            fence(Ordering::Acquire);
+            if state.generation.load(Ordering::Relaxed) == snap.generation {
+                return Some(snap);
+            }
+            test_yield();
+        }
+
+        None
+    }
+}
+
 #[cfg(all(test, feature = "loom"))]
 #[inline]
 fn test_yield() {
@@ -354,6 +664,8 @@ mod tests {
                 height: 0,
                 pitch_bytes: 0,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             })
             .is_none());
     }
@@ -373,6 +685,8 @@ mod tests {
                 height: 0,
                 pitch_bytes: 0,
                 format: SCANOUT_FORMAT_B8G8R8X8,
+                slot0_offset: 0,
+                slot1_offset: 0,
             });
         }));
         assert!(result.is_err());
@@ -417,6 +731,22 @@ mod tests {
             field_offset(core::ptr::addr_of!(state.format)),
             header_index::FORMAT * 4
         );
+        assert_eq!(
+            field_offset(core::ptr::addr_of!(state.flags)),
+            header_index::FLAGS * 4
+        );
+        assert_eq!(
+            field_offset(core::ptr::addr_of!(state.front)),
+            header_index::FRONT * 4
+        );
+        assert_eq!(
+            field_offset(core::ptr::addr_of!(state.slot0_offset)),
+            header_index::SLOT0_OFFSET * 4
+        );
+        assert_eq!(
+            field_offset(core::ptr::addr_of!(state.slot1_offset)),
+            header_index::SLOT1_OFFSET * 4
+        );
     }
 
     #[test]
@@ -461,6 +791,8 @@ mod tests {
             height: 2,
             pitch_bytes: 3,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
         let g1 = state.snapshot().generation;
         state.publish(ScanoutStateUpdate {
@@ -471,6 +803,8 @@ mod tests {
             height: 5,
             pitch_bytes: 6,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
         let g2 = state.snapshot().generation;
 
@@ -496,6 +830,8 @@ mod tests {
             height: 0,
             pitch_bytes: 0,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
         assert_eq!(g1, 0x7fff_ffff);
         assert_eq!(g1 & SCANOUT_STATE_GENERATION_BUSY_BIT, 0);
@@ -508,6 +844,8 @@ mod tests {
             height: 0,
             pitch_bytes: 0,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
         assert_eq!(g2, 0x0000_0000);
         assert_eq!(g2 & SCANOUT_STATE_GENERATION_BUSY_BIT, 0);
@@ -526,6 +864,8 @@ mod tests {
             height: 1,
             pitch_bytes: 2,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
 
         let start = Arc::new(std::sync::Barrier::new(2));
@@ -546,6 +886,8 @@ mod tests {
                     height: token.wrapping_add(1),
                     pitch_bytes: token.wrapping_add(2),
                     format: SCANOUT_FORMAT_B8G8R8X8,
+                    slot0_offset: 0,
+                    slot1_offset: 0,
                 });
             }
 
@@ -580,6 +922,160 @@ mod tests {
         writer.join().unwrap();
         reader.join().unwrap();
     }
+
+    fn frame_region(len_bytes: usize) -> (Vec<u32>, ScanoutFrameSlots) {
+        let mut backing = vec![0u32; len_bytes / 4];
+        // Safety: the returned handle is only used while `backing` is alive.
+        let slots = unsafe {
+            ScanoutFrameSlots::from_raw_parts(backing.as_mut_ptr() as *mut u8, len_bytes).unwrap()
+        };
+        (backing, slots)
+    }
+
+    #[test]
+    fn frame_slots_flip_front_and_advance_frame_seq() {
+        let state = ScanoutState::new();
+        let (_backing, slots) = frame_region(2 * 4 * 16 * 4);
+        let mut out = Vec::new();
+
+        assert!(slots.try_read_front(&state, &mut out).is_none());
+
+        slots
+            .try_publish_frame(&state, 4, 4, &[0x1111_1111; 16])
+            .unwrap();
+        let snap = slots.try_read_front(&state, &mut out).unwrap();
+        assert_eq!(snap.source, SCANOUT_SOURCE_FRAME_SLOTS);
+        assert_eq!(snap.format, SCANOUT_FORMAT_R8G8B8A8);
+        assert_eq!(snap.pitch_bytes, 16);
+        assert!(snap.is_double_buffered());
+        assert_eq!((snap.slot0_offset, snap.slot1_offset), (0, 256));
+        assert_eq!((snap.front_index, snap.frame_seq), (1, 1));
+        assert_eq!(out, vec![0x11; 64]);
+
+        slots
+            .try_publish_frame(&state, 4, 4, &[0x2222_2222; 16])
+            .unwrap();
+        let snap = slots.try_read_front(&state, &mut out).unwrap();
+        assert_eq!((snap.front_index, snap.frame_seq), (0, 2));
+        assert_eq!(out, vec![0x22; 64]);
+        assert_eq!(state.front(), (0, 2));
+    }
+
+    #[test]
+    fn frame_slots_fall_back_to_single_buffer_when_region_is_small() {
+        let state = ScanoutState::new();
+        let (_backing, slots) = frame_region(96);
+        let mut out = Vec::new();
+
+        assert_eq!(slots.slot_offsets(32), Some([0, 0]));
+        assert_eq!(slots.slot_offsets(96), Some([0, 0]));
+        assert_eq!(slots.slot_offsets(100), None);
+
+        slots
+            .try_publish_frame(&state, 4, 4, &[0x3333_3333; 16])
+            .unwrap();
+        let snap = slots.try_read_front(&state, &mut out).unwrap();
+        assert!(!snap.is_double_buffered());
+        assert_eq!(snap.flags & SCANOUT_STATE_FLAG_DOUBLE_BUFFERED, 0);
+        assert_eq!(snap.slot0_offset, snap.slot1_offset);
+        assert_eq!(out, vec![0x33; 64]);
+
+        // Too large for the region: nothing is published.
+        let generation = state.snapshot().generation;
+        assert!(slots.try_publish_frame(&state, 8, 8, &[0; 64]).is_none());
+        assert_eq!(state.snapshot().generation, generation);
+    }
+
+    #[test]
+    fn guest_memory_scanouts_are_not_double_buffered() {
+        let state = ScanoutState::new();
+        state.publish(ScanoutStateUpdate {
+            source: SCANOUT_SOURCE_WDDM,
+            base_paddr_lo: 0x1000,
+            base_paddr_hi: 0,
+            width: 1,
+            height: 1,
+            pitch_bytes: 4,
+            format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 64,
+        });
+        let snap = state.snapshot();
+        assert!(!snap.is_double_buffered());
+        assert_eq!(snap.frame_seq, 0);
+    }
+
+    #[test]
+    fn frame_slots_never_expose_torn_frames_under_concurrent_flips() {
+        const WIDTH: u32 = 256;
+        const HEIGHT: u32 = 32;
+        const FRAMES: u32 = 2_000;
+
+        let state = Arc::new(ScanoutState::new());
+        let frame_bytes = (WIDTH * HEIGHT * 4) as usize;
+        let (_backing, slots) = frame_region(2 * frame_bytes);
+
+        let start = Arc::new(std::sync::Barrier::new(2));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let writer_state = state.clone();
+        let writer_start = start.clone();
+        let writer_done = done.clone();
+        let writer = thread::spawn(move || {
+            writer_start.wait();
+            let mut pixels = vec![0u32; (WIDTH * HEIGHT) as usize];
+            for token in 1..=FRAMES {
+                // Each frame is a constant fill whose value encodes the frame number.
+                pixels.fill(u32::from_le_bytes([token as u8; 4]));
+                slots
+                    .try_publish_frame(&writer_state, WIDTH, HEIGHT, &pixels)
+                    .unwrap();
+                // Vary the flip rate so flips land both between and during reader copies.
+                for _ in 0..token % 97 {
+                    thread::yield_now();
+                }
+            }
+            writer_done.store(true, Ordering::SeqCst);
+        });
+
+        let reader_state = state.clone();
+        let reader_start = start.clone();
+        let reader_done = done.clone();
+        let reader = thread::spawn(move || {
+            reader_start.wait();
+            let mut out = Vec::new();
+            let mut last_seq = 0;
+            let mut frames_seen = 0u32;
+            loop {
+                let finished = reader_done.load(Ordering::SeqCst);
+                if let Some(snap) = slots.try_read_front(&reader_state, &mut out) {
+                    assert!(snap.is_double_buffered());
+                    assert_eq!(out.len(), frame_bytes);
+                    let fill = out[0];
+                    assert!(
+                        out.iter().all(|&b| b == fill),
+                        "torn frame observed at frame_seq {}",
+                        snap.frame_seq
+                    );
+                    // The fill value is the frame number (mod 256), which is also the sequence.
+                    assert_eq!(fill, snap.frame_seq as u8);
+                    assert!(snap.frame_seq >= last_seq, "frame_seq went backwards");
+                    if snap.frame_seq != last_seq {
+                        frames_seen += 1;
+                    }
+                    last_seq = snap.frame_seq;
+                }
+                if finished {
+                    break;
+                }
+            }
+            assert_eq!(last_seq, FRAMES);
+            assert!(frames_seen > 0);
+        });
+
+        writer.join().unwrap();
+        reader.join().unwrap();
+    }
 }
 
 #[cfg(all(test, feature = "loom"))]
@@ -613,6 +1109,8 @@ mod loom_tests {
                 height: generation.wrapping_add(14),
                 pitch_bytes: generation.wrapping_add(15),
                 format: generation.wrapping_add(16),
+                slot0_offset: generation.wrapping_add(17),
+                slot1_offset: generation.wrapping_add(18),
             }
         }
 
@@ -627,6 +1125,8 @@ mod loom_tests {
             assert_eq!(snap.height, g.wrapping_add(14));
             assert_eq!(snap.pitch_bytes, g.wrapping_add(15));
             assert_eq!(snap.format, g.wrapping_add(16));
+            assert_eq!(snap.slot0_offset, g.wrapping_add(17));
+            assert_eq!(snap.slot1_offset, g.wrapping_add(18));
         }
 
         fn assert_generation_monotonic(prev: u32, curr: u32, max_delta: u32) {
@@ -670,6 +1170,12 @@ mod loom_tests {
             state.height.store(init.height, Ordering::SeqCst);
             state.pitch_bytes.store(init.pitch_bytes, Ordering::SeqCst);
             state.format.store(init.format, Ordering::SeqCst);
+            state
+                .slot0_offset
+                .store(init.slot0_offset, Ordering::SeqCst);
+            state
+                .slot1_offset
+                .store(init.slot1_offset, Ordering::SeqCst);
             state.generation.store(initial_gen, Ordering::SeqCst);
 
             let writer_state = state.clone();
@@ -32,6 +32,11 @@ pub enum SnapshotError {
     #[error("guest RAM size mismatch (expected {expected} bytes, found {found} bytes)")]
     RamLenMismatch { expected: u64, found: u64 },
 
+    #[error(
+        "RAM transform mismatch (snapshot RAM transformed: {transformed}, transform supplied: {supplied})"
+    )]
+    RamTransformMismatch { transformed: bool, supplied: bool },
+
     #[error("lz4 decompression failed: {0}")]
     Lz4Decompress(#[from] lz4_flex::block::DecompressError),
 
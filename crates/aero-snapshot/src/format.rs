@@ -166,6 +166,24 @@ impl DeviceId {
     pub const GPU_VRAM: DeviceId = DeviceId(28);
     /// Guest-visible virtio-input (virtio-pci) tablet function state (PCI `00:0A.2`).
     pub const VIRTIO_INPUT_TABLET: DeviceId = DeviceId(29);
+    /// Legacy floppy disk controller state (82077AA at `0x3F0`; inner `FDC7`).
+    ///
+    /// Media bytes are not included; the floppy image is referenced via the `DISKS` section.
+    pub const FDC: DeviceId = DeviceId(30);
+    /// Legacy ISA DMA controller state (8237; inner `DMA8`).
+    pub const DMA: DeviceId = DeviceId(31);
+    /// Guest-visible virtio-balloon (virtio-pci) transport + device state (PCI `00:0E.0`; inner
+    /// `VPCI`).
+    pub const VIRTIO_BALLOON: DeviceId = DeviceId(32);
+    /// TPM 2.0 CRB interface state (`0xFED4_0000`; inner `TPMC`), including the opaque backend
+    /// state blob.
+    pub const TPM: DeviceId = DeviceId(33);
+    /// Guest-visible virtio-9p (virtio-pci) transport + device state, including the guest's fid
+    /// table (PCI `00:0F.0`; inner `VPCI`). Shared folder contents are host state and not included.
+    pub const VIRTIO_9P: DeviceId = DeviceId(34);
+    /// Guest-visible virtio-rng (virtio-pci) transport + device state, including the deterministic
+    /// entropy stream position (PCI `00:10.0`; inner `VPCI`).
+    pub const VIRTIO_RNG: DeviceId = DeviceId(35);
 
     pub fn name(self) -> Option<&'static str> {
         match self {
@@ -198,6 +216,12 @@ impl DeviceId {
             DeviceId::VIRTIO_INPUT_MOUSE => Some("VIRTIO_INPUT_MOUSE"),
             DeviceId::VIRTIO_INPUT_TABLET => Some("VIRTIO_INPUT_TABLET"),
             DeviceId::GPU_VRAM => Some("GPU_VRAM"),
+            DeviceId::FDC => Some("FDC"),
+            DeviceId::DMA => Some("DMA"),
+            DeviceId::VIRTIO_BALLOON => Some("VIRTIO_BALLOON"),
+            DeviceId::TPM => Some("TPM"),
+            DeviceId::VIRTIO_9P => Some("VIRTIO_9P"),
+            DeviceId::VIRTIO_RNG => Some("VIRTIO_RNG"),
             _ => None,
         }
     }
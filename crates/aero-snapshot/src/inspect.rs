@@ -3,7 +3,10 @@ use std::io::{Read, Seek, SeekFrom};
 use crate::error::{Result, SnapshotError};
 use crate::format::{SectionId, SNAPSHOT_ENDIANNESS_LITTLE, SNAPSHOT_MAGIC, SNAPSHOT_VERSION_V1};
 use crate::io::ReadLeExt;
-use crate::ram::{Compression, RamMode, MAX_CHUNK_SIZE, MAX_PAGE_SIZE};
+use crate::ram::{
+    read_transform_metadata, Compression, RamMode, MAX_CHUNK_SIZE, MAX_PAGE_SIZE,
+    RAM_FLAG_TRANSFORMED, RAM_FLAG_ZERO_PAGES, RAM_SECTION_VERSION, RAM_SECTION_VERSION_FLAGS,
+};
 use crate::types::SnapshotMeta;
 
 const SECTION_HEADER_LEN: u64 = 4 + 2 + 2 + 8;
@@ -17,7 +20,7 @@ pub struct SnapshotSectionInfo {
     pub len: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RamHeaderSummary {
     pub total_len: u64,
     pub page_size: u32,
@@ -25,6 +28,11 @@ pub struct RamHeaderSummary {
     pub compression: Compression,
     pub chunk_size: Option<u32>,
     pub dirty_count: Option<u64>,
+    /// Caller-supplied metadata of a transformed RAM section (see
+    /// [`crate::save_snapshot_with_ram_transform`]); `None` if the RAM is not transformed.
+    pub transform_metadata: Option<Vec<u8>>,
+    /// All-zero pages are stored as zero-run records (see [`crate::RAM_FLAG_ZERO_PAGES`]).
+    pub zero_pages: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -82,7 +90,11 @@ pub fn inspect_snapshot<R: Read + Seek>(r: &mut R) -> Result<SnapshotIndex> {
                 SnapshotMeta::decode(&mut limited)?
             };
             meta = Some(decoded);
-        } else if id == SectionId::RAM && section_version == 1 && ram.is_none() {
+        } else if id == SectionId::RAM
+            && (section_version == RAM_SECTION_VERSION
+                || section_version == RAM_SECTION_VERSION_FLAGS)
+            && ram.is_none()
+        {
             let summary = {
                 let mut limited = r.take(len);
                 inspect_ram_section(&mut limited, len)?
@@ -178,7 +190,19 @@ fn inspect_ram_section<R: Read>(r: &mut R, section_len: u64) -> Result<RamHeader
 
     let mode = RamMode::from_u8(r.read_u8()?)?;
     let compression = Compression::from_u8(r.read_u8()?)?;
-    let _reserved = r.read_u16_le()?;
+    let flags = r.read_u16_le()?;
+    let zero_pages = flags & RAM_FLAG_ZERO_PAGES != 0;
+    let mut header_len = 16u64;
+    let transform_metadata = if flags & RAM_FLAG_TRANSFORMED != 0 {
+        if section_len < header_len + 4 {
+            return Err(SnapshotError::Corrupt("truncated ram section"));
+        }
+        let metadata = read_transform_metadata(r)?;
+        header_len += 4 + metadata.len() as u64;
+        Some(metadata)
+    } else {
+        None
+    };
 
     let mut summary = RamHeaderSummary {
         total_len,
@@ -187,11 +211,13 @@ fn inspect_ram_section<R: Read>(r: &mut R, section_len: u64) -> Result<RamHeader
         compression,
         chunk_size: None,
         dirty_count: None,
+        transform_metadata,
+        zero_pages,
     };
 
     match mode {
         RamMode::Full => {
-            if section_len < 20 {
+            if section_len < header_len + 4 {
                 return Err(SnapshotError::Corrupt("truncated ram section"));
             }
             let chunk_size = r.read_u32_le()?;
@@ -199,11 +225,17 @@ fn inspect_ram_section<R: Read>(r: &mut R, section_len: u64) -> Result<RamHeader
                 return Err(SnapshotError::Corrupt("invalid chunk size"));
             }
 
-            let chunk_count = total_len
-                .checked_add(chunk_size as u64 - 1)
-                .ok_or(SnapshotError::Corrupt("chunk count overflow"))?
-                / chunk_size as u64;
-            let min_payload_len = 20u64
+            // A single zero-page run can cover all of RAM, so only unflagged sections have a
+            // per-chunk minimum size.
+            let chunk_count = if zero_pages {
+                0
+            } else {
+                total_len
+                    .checked_add(chunk_size as u64 - 1)
+                    .ok_or(SnapshotError::Corrupt("chunk count overflow"))?
+                    / chunk_size as u64
+            };
+            let min_payload_len = (header_len + 4)
                 .checked_add(
                     chunk_count
                         .checked_mul(8)
@@ -217,14 +249,16 @@ fn inspect_ram_section<R: Read>(r: &mut R, section_len: u64) -> Result<RamHeader
             summary.chunk_size = Some(chunk_size);
         }
         RamMode::Dirty => {
-            if section_len < 24 {
+            if section_len < header_len + 8 {
                 return Err(SnapshotError::Corrupt("truncated ram section"));
             }
             let dirty_count = r.read_u64_le()?;
-            let min_payload_len = 24u64
+            // page_idx + uncompressed_len, plus stored_len or (with zero pages) the record kind.
+            let min_entry_len = if zero_pages { 13 } else { 16 };
+            let min_payload_len = (header_len + 8)
                 .checked_add(
                     dirty_count
-                        .checked_mul(16)
+                        .checked_mul(min_entry_len)
                         .ok_or(SnapshotError::Corrupt("dirty count overflow"))?,
                 )
                 .ok_or(SnapshotError::Corrupt("dirty count overflow"))?;
@@ -24,7 +24,10 @@ pub use crate::format::{
 pub use crate::inspect::{
     inspect_snapshot, read_snapshot_meta, RamHeaderSummary, SnapshotIndex, SnapshotSectionInfo,
 };
-pub use crate::ram::{Compression, RamMode, RamWriteOptions};
+pub use crate::ram::{
+    Compression, RamBlockTransform, RamMode, RamSectionStats, RamWriteOptions,
+    RAM_FLAG_TRANSFORMED, RAM_FLAG_ZERO_PAGES,
+};
 pub use crate::types::{
     CpuInternalState, CpuMode, CpuState, DeviceState, DiskOverlayRef, DiskOverlayRefs, FpuState,
     MmuState, SegmentState, SnapshotMeta, VcpuMmuSnapshot, VcpuSnapshot,
@@ -189,6 +192,51 @@ pub fn save_snapshot<W: Write + Seek, S: SnapshotSource>(
     source: &mut S,
     options: SaveOptions,
 ) -> Result<()> {
+    save_snapshot_impl(w, source, options, None).map(|_| ())
+}
+
+/// Like [`save_snapshot`], additionally returning the raw and encoded sizes of the `RAM` section so
+/// callers can report the savings of compression and zero-page elision.
+pub fn save_snapshot_with_stats<W: Write + Seek, S: SnapshotSource>(
+    w: &mut W,
+    source: &mut S,
+    options: SaveOptions,
+) -> Result<RamSectionStats> {
+    save_snapshot_impl(w, source, options, None)
+}
+
+/// Save a snapshot, passing every encoded RAM block through `transform` (e.g. encryption).
+///
+/// RAM is read, compressed and transformed one block at a time, so memory use stays bounded by
+/// the block size regardless of guest RAM size. `metadata` is stored verbatim in the RAM section
+/// header (e.g. a nonce or key-derivation salt) and can be read back with [`inspect_snapshot`]
+/// before restoring with [`restore_snapshot_with_ram_transform`]. Only the `RAM` section is
+/// transformed; all other sections are written as usual.
+pub fn save_snapshot_with_ram_transform<W: Write + Seek, S: SnapshotSource>(
+    w: &mut W,
+    source: &mut S,
+    options: SaveOptions,
+    metadata: &[u8],
+    transform: RamBlockTransform<'_>,
+) -> Result<()> {
+    save_snapshot_impl(
+        w,
+        source,
+        options,
+        Some(ram::RamTransform {
+            metadata,
+            apply: transform,
+        }),
+    )
+    .map(|_| ())
+}
+
+fn save_snapshot_impl<W: Write + Seek, S: SnapshotSource>(
+    w: &mut W,
+    source: &mut S,
+    options: SaveOptions,
+    ram_transform: Option<ram::RamTransform<'_>>,
+) -> Result<RamSectionStats> {
     if options.ram.mode == RamMode::Dirty {
         let source_page_size = source.dirty_page_size();
         if options.ram.page_size != source_page_size {
@@ -351,7 +399,9 @@ pub fn save_snapshot<W: Write + Seek, S: SnapshotSource>(
         disks.encode(w)
     })?;
 
-    write_section(w, SectionId::RAM, 1, 0, |w| {
+    let ram_version = ram::ram_section_version(&options.ram, ram_transform.is_some());
+    let mut ram_stats = RamSectionStats::default();
+    write_section(w, SectionId::RAM, ram_version, 0, |w| {
         let total_len = source.ram_len() as u64;
 
         let dirty_pages = match options.ram.mode {
@@ -381,20 +431,22 @@ pub fn save_snapshot<W: Write + Seek, S: SnapshotSource>(
             }
         };
 
-        ram::encode_ram_section(
+        ram_stats = ram::encode_ram_section(
             w,
             total_len,
             options.ram,
             dirty_pages.as_deref(),
+            ram_transform,
             |offset, buf| source.read_ram(offset, buf),
-        )
+        )?;
+        Ok(())
     })?;
 
-    Ok(())
+    Ok(ram_stats)
 }
 
 pub fn restore_snapshot<R: Read, T: SnapshotTarget>(r: &mut R, target: &mut T) -> Result<()> {
-    restore_snapshot_impl(r, target, None, None)
+    restore_snapshot_impl(r, target, None, None, None)
 }
 
 /// Restore a snapshot, validating the dirty-page parent contract without requiring `Seek`.
@@ -411,13 +463,36 @@ pub fn restore_snapshot_checked<R: Read, T: SnapshotTarget>(
     target: &mut T,
     opts: RestoreOptions,
 ) -> Result<()> {
-    restore_snapshot_impl(r, target, Some(opts), None)
+    restore_snapshot_impl(r, target, Some(opts), None, None)
 }
 
 pub fn restore_snapshot_with_options<R: Read + Seek, T: SnapshotTarget>(
     r: &mut R,
     target: &mut T,
     opts: RestoreOptions,
+) -> Result<()> {
+    restore_snapshot_seekable(r, target, opts, None)
+}
+
+/// Restore a snapshot written by [`save_snapshot_with_ram_transform`], passing every stored RAM
+/// block through `transform` (the inverse of the save-side transform) before decompression.
+///
+/// Fails with [`SnapshotError::RamTransformMismatch`] if the snapshot RAM was not transformed.
+/// Otherwise behaves like [`restore_snapshot_with_options`].
+pub fn restore_snapshot_with_ram_transform<R: Read + Seek, T: SnapshotTarget>(
+    r: &mut R,
+    target: &mut T,
+    opts: RestoreOptions,
+    transform: RamBlockTransform<'_>,
+) -> Result<()> {
+    restore_snapshot_seekable(r, target, opts, Some(transform))
+}
+
+fn restore_snapshot_seekable<R: Read + Seek, T: SnapshotTarget>(
+    r: &mut R,
+    target: &mut T,
+    opts: RestoreOptions,
+    ram_transform: Option<RamBlockTransform<'_>>,
 ) -> Result<()> {
     let start_pos = r.stream_position()?;
 
@@ -433,7 +508,7 @@ pub fn restore_snapshot_with_options<R: Read + Seek, T: SnapshotTarget>(
     }
 
     r.seek(SeekFrom::Start(start_pos))?;
-    restore_snapshot_impl(r, target, Some(opts), prescan.meta)
+    restore_snapshot_impl(r, target, Some(opts), prescan.meta, ram_transform)
 }
 
 #[derive(Debug, Clone)]
@@ -458,7 +533,7 @@ fn prescan_snapshot<R: Read + Seek>(r: &mut R) -> Result<SnapshotPrescan> {
                 }
             }
             id if id == SectionId::RAM => {
-                if header.version == 1 {
+                if is_supported_ram_version(header.version) {
                     let mut section_reader = r.take(header.len);
                     let _total_len = section_reader.read_u64_le()?;
                     let _page_size = section_reader.read_u32_le()?;
@@ -488,6 +563,7 @@ fn restore_snapshot_impl<R: Read, T: SnapshotTarget>(
     target: &mut T,
     opts: Option<RestoreOptions>,
     prescanned_meta: Option<SnapshotMeta>,
+    mut ram_transform: Option<RamBlockTransform<'_>>,
 ) -> Result<()> {
     read_file_header(r)?;
     target.pre_restore();
@@ -970,7 +1046,7 @@ fn restore_snapshot_impl<R: Read, T: SnapshotTarget>(
                 }
             }
             id if id == SectionId::RAM => {
-                if header.version == 1 {
+                if is_supported_ram_version(header.version) {
                     if seen_ram {
                         return Err(SnapshotError::Corrupt("duplicate RAM section"));
                     }
@@ -1004,9 +1080,12 @@ fn restore_snapshot_impl<R: Read, T: SnapshotTarget>(
                     {
                         let mut replay =
                             std::io::Cursor::new(ram_header).chain(&mut section_reader);
-                        ram::decode_ram_section_into(&mut replay, expected_len, |offset, data| {
-                            target.write_ram(offset, data)
-                        })?;
+                        ram::decode_ram_section_into(
+                            &mut replay,
+                            expected_len,
+                            ram_transform.take(),
+                            |offset, data| target.write_ram(offset, data),
+                        )?;
                     }
 
                     seen_ram = true;
@@ -1037,6 +1116,10 @@ fn restore_snapshot_impl<R: Read, T: SnapshotTarget>(
     Ok(())
 }
 
+fn is_supported_ram_version(version: u16) -> bool {
+    version == ram::RAM_SECTION_VERSION || version == ram::RAM_SECTION_VERSION_FLAGS
+}
+
 #[derive(Debug, Clone, Copy)]
 struct SectionHeader {
     id: SectionId,
@@ -39,3 +39,10 @@ pub const MAX_RAM_PAGE_SIZE: u32 = 2 * 1024 * 1024;
 
 /// Maximum supported RAM chunk size in bytes.
 pub const MAX_RAM_CHUNK_SIZE: u32 = 64 * 1024 * 1024;
+
+/// Maximum length in bytes of the caller-supplied RAM transform metadata blob.
+pub const MAX_RAM_TRANSFORM_METADATA_LEN: u32 = 64 * 1024;
+
+/// Maximum number of bytes a RAM transform may add to a single payload block (e.g. an
+/// authentication tag or per-block nonce).
+pub const MAX_RAM_TRANSFORM_OVERHEAD: u32 = 4 * 1024;
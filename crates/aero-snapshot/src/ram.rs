@@ -1,4 +1,4 @@
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 
 use crate::error::{Result, SnapshotError};
 use crate::io::{ReadLeExt, WriteLeExt};
@@ -6,6 +6,48 @@ use crate::io::{ReadLeExt, WriteLeExt};
 pub(crate) const MAX_PAGE_SIZE: u32 = crate::limits::MAX_RAM_PAGE_SIZE;
 pub(crate) const MAX_CHUNK_SIZE: u32 = crate::limits::MAX_RAM_CHUNK_SIZE;
 
+/// `RAM` section version written when no header flags are set.
+pub(crate) const RAM_SECTION_VERSION: u16 = 1;
+/// `RAM` section version written when any header flag is set. Version 1 readers ignored the flags
+/// field, so flagged sections use a new version to make older readers skip them instead of
+/// misinterpreting the payload.
+pub(crate) const RAM_SECTION_VERSION_FLAGS: u16 = 2;
+
+/// RAM header flag: payload blocks were passed through a caller-supplied transform and the header
+/// is followed by the transform metadata blob.
+pub const RAM_FLAG_TRANSFORMED: u16 = 1 << 0;
+/// RAM header flag: every block is preceded by a record kind byte, and runs of all-zero pages are
+/// stored as zero-run records without payload.
+pub const RAM_FLAG_ZERO_PAGES: u16 = 1 << 1;
+
+const RAM_FLAGS_KNOWN: u16 = RAM_FLAG_TRANSFORMED | RAM_FLAG_ZERO_PAGES;
+
+/// Record kind (with [`RAM_FLAG_ZERO_PAGES`]): an encoded payload block follows.
+const RAM_RECORD_DATA: u8 = 0;
+/// Record kind (with [`RAM_FLAG_ZERO_PAGES`]): the covered bytes are all zero.
+const RAM_RECORD_ZERO: u8 = 1;
+
+/// Size of the shared zero buffer used to restore zero-page runs.
+const ZERO_FILL_LEN: usize = 64 * 1024;
+static ZERO_FILL: [u8; ZERO_FILL_LEN] = [0; ZERO_FILL_LEN];
+
+/// Caller-supplied transform applied to RAM payload blocks.
+///
+/// The transform receives one encoded block (after compression on save, as stored on restore) and
+/// appends its output to the provided (cleared) buffer. Blocks are at most
+/// `RamWriteOptions::chunk_size` (full snapshots) or `RamWriteOptions::page_size` (dirty
+/// snapshots) bytes plus compression overhead, and the output may grow each block by at most
+/// [`crate::limits::MAX_RAM_TRANSFORM_OVERHEAD`] bytes. Blocks are visited in file order, so a
+/// transform may derive per-block state (e.g. a nonce counter) from the call index. Zero-page
+/// runs (see [`RAM_FLAG_ZERO_PAGES`]) carry no payload and are not passed to the transform.
+pub type RamBlockTransform<'a> = &'a mut dyn FnMut(&[u8], &mut Vec<u8>);
+
+/// Save-side transform together with the opaque metadata stored in the RAM header.
+pub(crate) struct RamTransform<'a> {
+    pub(crate) metadata: &'a [u8],
+    pub(crate) apply: RamBlockTransform<'a>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum RamMode {
@@ -46,6 +88,11 @@ pub struct RamWriteOptions {
     pub compression: Compression,
     pub page_size: u32,
     pub chunk_size: u32,
+    /// Store runs of all-zero pages as zero-run records instead of (compressed) payload.
+    ///
+    /// Sets [`RAM_FLAG_ZERO_PAGES`], which requires a reader that understands `RAM` section
+    /// version 2.
+    pub zero_pages: bool,
 }
 
 impl Default for RamWriteOptions {
@@ -55,17 +102,40 @@ impl Default for RamWriteOptions {
             compression: Compression::Lz4,
             page_size: 4096,
             chunk_size: 1024 * 1024,
+            zero_pages: true,
         }
     }
 }
 
-pub fn encode_ram_section<W: Write>(
+/// Size summary of an encoded `RAM` section (see [`crate::save_snapshot_with_stats`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RamSectionStats {
+    /// Guest RAM bytes covered by the section: all of RAM for full snapshots, the dirty pages for
+    /// dirty snapshots.
+    pub raw_len: u64,
+    /// Bytes of `raw_len` stored as zero-page runs.
+    pub zero_len: u64,
+    /// Encoded length of the section payload, including headers.
+    pub stored_len: u64,
+}
+
+/// Version of the `RAM` section written for `opts` (see [`RAM_SECTION_VERSION_FLAGS`]).
+pub(crate) fn ram_section_version(opts: &RamWriteOptions, transformed: bool) -> u16 {
+    if transformed || opts.zero_pages {
+        RAM_SECTION_VERSION_FLAGS
+    } else {
+        RAM_SECTION_VERSION
+    }
+}
+
+pub(crate) fn encode_ram_section<W: Write>(
     w: &mut W,
     total_len: u64,
     opts: RamWriteOptions,
     dirty_pages: Option<&[u64]>,
+    transform: Option<RamTransform<'_>>,
     read_ram: impl FnMut(u64, &mut [u8]) -> Result<()>,
-) -> Result<()> {
+) -> Result<RamSectionStats> {
     if opts.page_size == 0 || opts.page_size > MAX_PAGE_SIZE {
         return Err(SnapshotError::Corrupt("invalid page size"));
     }
@@ -73,20 +143,151 @@ pub fn encode_ram_section<W: Write>(
         return Err(SnapshotError::Corrupt("invalid chunk size"));
     }
 
+    let mut w = CountingWriter {
+        inner: w,
+        written: 0,
+    };
+    let w = &mut w;
+    let mut flags = 0u16;
+    if transform.is_some() {
+        flags |= RAM_FLAG_TRANSFORMED;
+    }
+    if opts.zero_pages {
+        flags |= RAM_FLAG_ZERO_PAGES;
+    }
     w.write_u64_le(total_len)?;
     w.write_u32_le(opts.page_size)?;
     w.write_u8(opts.mode as u8)?;
     w.write_u8(opts.compression as u8)?;
-    w.write_u16_le(0)?; // reserved
+    w.write_u16_le(flags)?;
+    let apply = match transform {
+        Some(transform) => {
+            if transform.metadata.len() > crate::limits::MAX_RAM_TRANSFORM_METADATA_LEN as usize {
+                return Err(SnapshotError::Corrupt("ram transform metadata too large"));
+            }
+            w.write_u32_le(transform.metadata.len() as u32)?;
+            w.write_bytes(transform.metadata)?;
+            Some(transform.apply)
+        }
+        None => None,
+    };
 
+    let block_len = match opts.mode {
+        RamMode::Full => opts.chunk_size,
+        RamMode::Dirty => opts.page_size,
+    };
+    let mut encoder = BlockEncoder::new(opts.compression, block_len, apply)?;
+    let mut stats = RamSectionStats::default();
     match opts.mode {
-        RamMode::Full => encode_full(w, total_len, opts, read_ram),
+        RamMode::Full => encode_full(w, total_len, opts, &mut encoder, &mut stats, read_ram)?,
         RamMode::Dirty => {
             let dirty_pages = dirty_pages.ok_or(SnapshotError::Corrupt(
                 "dirty ram mode requires dirty page list",
             ))?;
-            encode_dirty(w, total_len, opts, dirty_pages, read_ram)
+            encode_dirty(
+                w,
+                total_len,
+                opts,
+                dirty_pages,
+                &mut encoder,
+                &mut stats,
+                read_ram,
+            )?
+        }
+    }
+    stats.stored_len = w.written;
+    Ok(stats)
+}
+
+/// Tracks the number of bytes written to the section for [`RamSectionStats::stored_len`].
+struct CountingWriter<'a, W> {
+    inner: &'a mut W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn is_zero(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&b| b == 0)
+}
+
+/// Compresses (and optionally transforms) RAM blocks using buffers reused across blocks.
+struct BlockEncoder<'a> {
+    compression: Compression,
+    compressed: Vec<u8>,
+    transform: Option<RamBlockTransform<'a>>,
+    transformed: Vec<u8>,
+}
+
+impl<'a> BlockEncoder<'a> {
+    fn new(
+        compression: Compression,
+        max_block_len: u32,
+        transform: Option<RamBlockTransform<'a>>,
+    ) -> Result<Self> {
+        let mut compressed = Vec::new();
+        if compression == Compression::Lz4 {
+            let max = max_lz4_compressed_len(max_block_len) as usize;
+            compressed
+                .try_reserve_exact(max)
+                .map_err(|_| SnapshotError::OutOfMemory { len: max })?;
+            compressed.resize(max, 0);
+        }
+        Ok(Self {
+            compression,
+            compressed,
+            transform,
+            transformed: Vec::new(),
+        })
+    }
+
+    /// Encode one block, returning the bytes to store.
+    fn encode<'b>(&'b mut self, block: &'b [u8]) -> Result<&'b [u8]> {
+        let encoded: &[u8] = match self.compression {
+            Compression::None => block,
+            Compression::Lz4 => {
+                let written = lz4_compress_into(block, &mut self.compressed)?;
+                &self.compressed[..written]
+            }
+        };
+        let Some(apply) = self.transform.as_mut() else {
+            return Ok(encoded);
+        };
+
+        self.transformed.clear();
+        apply(encoded, &mut self.transformed);
+        let max = max_stored_len(self.compression, block.len() as u32, true);
+        if self.transformed.len() as u64 > max {
+            return Err(SnapshotError::Corrupt("ram transform output too large"));
         }
+        Ok(&self.transformed)
+    }
+
+    /// Encode one block and write it as `uncompressed_len: u32`, `stored_len: u32`, payload.
+    fn write_block<W: Write>(&mut self, w: &mut W, block: &[u8]) -> Result<()> {
+        let uncompressed_len: u32 = block
+            .len()
+            .try_into()
+            .map_err(|_| SnapshotError::Corrupt("chunk too large"))?;
+        let payload = self.encode(block)?;
+        let stored_len: u32 = payload
+            .len()
+            .try_into()
+            .map_err(|_| SnapshotError::Corrupt("compressed chunk too large"))?;
+        w.write_u32_le(uncompressed_len)?;
+        w.write_u32_le(stored_len)?;
+        w.write_bytes(payload)?;
+        Ok(())
     }
 }
 
@@ -94,6 +295,8 @@ fn encode_full<W: Write>(
     w: &mut W,
     total_len: u64,
     opts: RamWriteOptions,
+    encoder: &mut BlockEncoder<'_>,
+    stats: &mut RamSectionStats,
     mut read_ram: impl FnMut(u64, &mut [u8]) -> Result<()>,
 ) -> Result<()> {
     w.write_u32_le(opts.chunk_size)?;
@@ -107,44 +310,57 @@ fn encode_full<W: Write>(
             len: chunk_size_usize,
         })?;
     buf.resize(chunk_size_usize, 0);
-    let mut compressed = Vec::new();
-    if opts.compression == Compression::Lz4 {
-        let max = max_lz4_compressed_len(opts.chunk_size) as usize;
-        compressed
-            .try_reserve_exact(max)
-            .map_err(|_| SnapshotError::OutOfMemory { len: max })?;
-        compressed.resize(max, 0);
-    }
+    let page_size = opts.page_size as usize;
+    // Zero pages are coalesced into a single record even across chunk boundaries.
+    let mut zero_run = 0u64;
     while offset < total_len {
         let remaining = total_len - offset;
         let uncompressed_len = (remaining.min(chunk_size)) as usize;
         let buf_slice = &mut buf[..uncompressed_len];
         read_ram(offset, buf_slice)?;
+        stats.raw_len += uncompressed_len as u64;
 
-        let uncompressed_len_u32: u32 = uncompressed_len
-            .try_into()
-            .map_err(|_| SnapshotError::Corrupt("chunk too large"))?;
-        let compressed_len_u32: u32;
-        let payload: &[u8];
-        match opts.compression {
-            Compression::None => {
-                compressed_len_u32 = uncompressed_len_u32;
-                payload = buf_slice;
+        if !opts.zero_pages {
+            encoder.write_block(w, buf_slice)?;
+            offset += uncompressed_len as u64;
+            continue;
+        }
+
+        // Split the chunk into alternating runs of zero and non-zero pages.
+        let mut start = 0;
+        while start < uncompressed_len {
+            let zero = is_zero(&buf_slice[start..(start + page_size).min(uncompressed_len)]);
+            let mut end = start;
+            while end < uncompressed_len {
+                let page_end = (end + page_size).min(uncompressed_len);
+                if is_zero(&buf_slice[end..page_end]) != zero {
+                    break;
+                }
+                end = page_end;
             }
-            Compression::Lz4 => {
-                let written = lz4_compress_into(buf_slice, &mut compressed)?;
-                compressed_len_u32 = written
-                    .try_into()
-                    .map_err(|_| SnapshotError::Corrupt("compressed chunk too large"))?;
-                payload = &compressed[..written];
+            if zero {
+                zero_run += (end - start) as u64;
+                stats.zero_len += (end - start) as u64;
+            } else {
+                write_zero_run(w, &mut zero_run)?;
+                w.write_u8(RAM_RECORD_DATA)?;
+                encoder.write_block(w, &buf_slice[start..end])?;
             }
+            start = end;
         }
-        w.write_u32_le(uncompressed_len_u32)?;
-        w.write_u32_le(compressed_len_u32)?;
-        w.write_bytes(payload)?;
-
         offset += uncompressed_len as u64;
     }
+    write_zero_run(w, &mut zero_run)
+}
+
+/// Flush a pending zero-page run as a `RAM_RECORD_ZERO` record (`len: u64`).
+fn write_zero_run<W: Write>(w: &mut W, zero_run: &mut u64) -> Result<()> {
+    if *zero_run == 0 {
+        return Ok(());
+    }
+    w.write_u8(RAM_RECORD_ZERO)?;
+    w.write_u64_le(*zero_run)?;
+    *zero_run = 0;
     Ok(())
 }
 
@@ -153,6 +369,8 @@ fn encode_dirty<W: Write>(
     total_len: u64,
     opts: RamWriteOptions,
     dirty_pages: &[u64],
+    encoder: &mut BlockEncoder<'_>,
+    stats: &mut RamSectionStats,
     mut read_ram: impl FnMut(u64, &mut [u8]) -> Result<()>,
 ) -> Result<()> {
     // The `dirty_pages` list can originate from runtime tracking; keep encoding deterministic by
@@ -175,14 +393,6 @@ fn encode_dirty<W: Write>(
             len: page_size_usize,
         })?;
     buf.resize(page_size_usize, 0);
-    let mut compressed = Vec::new();
-    if opts.compression == Compression::Lz4 {
-        let max = max_lz4_compressed_len(opts.page_size) as usize;
-        compressed
-            .try_reserve_exact(max)
-            .map_err(|_| SnapshotError::OutOfMemory { len: max })?;
-        compressed.resize(max, 0);
-    }
     for page_idx in dirty_pages {
         let offset = page_idx
             .checked_mul(page_size)
@@ -195,24 +405,19 @@ fn encode_dirty<W: Write>(
         let uncompressed_len = (remaining.min(page_size)) as usize;
         let buf_slice = &mut buf[..uncompressed_len];
         read_ram(offset, buf_slice)?;
+        stats.raw_len += uncompressed_len as u64;
 
-        let compressed_len_u32: u32;
-        let payload: &[u8];
-        match opts.compression {
-            Compression::None => {
-                compressed_len_u32 = uncompressed_len as u32;
-                payload = buf_slice;
-            }
-            Compression::Lz4 => {
-                let written = lz4_compress_into(buf_slice, &mut compressed)?;
-                compressed_len_u32 = written as u32;
-                payload = &compressed[..written];
-            }
-        }
         w.write_u64_le(page_idx)?;
-        w.write_u32_le(uncompressed_len as u32)?;
-        w.write_u32_le(compressed_len_u32)?;
-        w.write_bytes(payload)?;
+        if !opts.zero_pages {
+            encoder.write_block(w, buf_slice)?;
+        } else if is_zero(buf_slice) {
+            stats.zero_len += uncompressed_len as u64;
+            w.write_u8(RAM_RECORD_ZERO)?;
+            w.write_u32_le(uncompressed_len as u32)?;
+        } else {
+            w.write_u8(RAM_RECORD_DATA)?;
+            encoder.write_block(w, buf_slice)?;
+        }
     }
     Ok(())
 }
@@ -223,6 +428,19 @@ fn max_lz4_compressed_len(uncompressed_len: u32) -> u32 {
     lz4_flex::block::get_maximum_output_size(uncompressed_len as usize) as u32
 }
 
+/// Upper bound on the stored length of a block holding `uncompressed_len` bytes of RAM.
+fn max_stored_len(compression: Compression, uncompressed_len: u32, transformed: bool) -> u64 {
+    let max = match compression {
+        Compression::None => u64::from(uncompressed_len),
+        Compression::Lz4 => u64::from(max_lz4_compressed_len(uncompressed_len)),
+    };
+    if transformed {
+        max + u64::from(crate::limits::MAX_RAM_TRANSFORM_OVERHEAD)
+    } else {
+        max
+    }
+}
+
 fn lz4_compress_into(input: &[u8], output: &mut [u8]) -> Result<usize> {
     lz4_flex::block::compress_into(input, output)
         .map_err(|_| SnapshotError::Corrupt("lz4 compression failed"))
@@ -233,9 +451,10 @@ fn lz4_decompress_into(compressed: &[u8], output: &mut [u8]) -> Result<()> {
     Ok(())
 }
 
-pub fn decode_ram_section_into<R: Read>(
+pub(crate) fn decode_ram_section_into<R: Read>(
     r: &mut R,
     expected_total_len: u64,
+    transform: Option<RamBlockTransform<'_>>,
     write_ram: impl FnMut(u64, &[u8]) -> Result<()>,
 ) -> Result<()> {
     let total_len = r.read_u64_le()?;
@@ -251,18 +470,119 @@ pub fn decode_ram_section_into<R: Read>(
     }
     let mode = RamMode::from_u8(r.read_u8()?)?;
     let compression = Compression::from_u8(r.read_u8()?)?;
-    let _reserved = r.read_u16_le()?;
+    let flags = r.read_u16_le()?;
+    if flags & !RAM_FLAGS_KNOWN != 0 {
+        return Err(SnapshotError::Corrupt("unsupported ram flags"));
+    }
+    let transformed = flags & RAM_FLAG_TRANSFORMED != 0;
+    if transformed != transform.is_some() {
+        return Err(SnapshotError::RamTransformMismatch {
+            transformed,
+            supplied: transform.is_some(),
+        });
+    }
+    if transformed {
+        // The metadata is only interpreted by the caller (see `inspect_snapshot`).
+        read_transform_metadata(r)?;
+    }
 
+    let mut decoder = BlockDecoder {
+        compression,
+        zero_pages: flags & RAM_FLAG_ZERO_PAGES != 0,
+        transform,
+        stored: Vec::new(),
+        plain: Vec::new(),
+        decompressed: Vec::new(),
+    };
     match mode {
-        RamMode::Full => decode_full(r, total_len, compression, write_ram),
-        RamMode::Dirty => decode_dirty(r, total_len, page_size, compression, write_ram),
+        RamMode::Full => decode_full(r, total_len, &mut decoder, write_ram),
+        RamMode::Dirty => decode_dirty(r, total_len, page_size, &mut decoder, write_ram),
+    }
+}
+
+pub(crate) fn read_transform_metadata<R: Read>(r: &mut R) -> Result<Vec<u8>> {
+    let len = r.read_u32_le()?;
+    if len > crate::limits::MAX_RAM_TRANSFORM_METADATA_LEN {
+        return Err(SnapshotError::Corrupt("ram transform metadata too large"));
+    }
+    r.read_exact_vec(len as usize)
+}
+
+/// Reverses [`BlockEncoder`], reusing its buffers across blocks.
+struct BlockDecoder<'a> {
+    compression: Compression,
+    /// Blocks are preceded by a record kind byte (see [`RAM_FLAG_ZERO_PAGES`]).
+    zero_pages: bool,
+    transform: Option<RamBlockTransform<'a>>,
+    stored: Vec<u8>,
+    plain: Vec<u8>,
+    decompressed: Vec<u8>,
+}
+
+impl BlockDecoder<'_> {
+    /// Read the kind byte of the next record; returns `true` for a zero-page run.
+    ///
+    /// Without [`RAM_FLAG_ZERO_PAGES`] every record is a data block and nothing is read.
+    fn read_is_zero_record<R: Read>(&self, r: &mut R) -> Result<bool> {
+        if !self.zero_pages {
+            return Ok(false);
+        }
+        match r.read_u8()? {
+            RAM_RECORD_DATA => Ok(false),
+            RAM_RECORD_ZERO => Ok(true),
+            _ => Err(SnapshotError::Corrupt("invalid ram record kind")),
+        }
+    }
+
+    /// Read one stored block and return the `uncompressed_len` bytes of RAM it holds.
+    fn decode<R: Read>(&mut self, r: &mut R, uncompressed_len: u32) -> Result<&[u8]> {
+        let stored_len = r.read_u32_le()?;
+        let encoded: &[u8] = match self.transform.as_mut() {
+            None => {
+                validate_compressed_len(self.compression, uncompressed_len, stored_len)?;
+                r.read_exact_into_vec(&mut self.stored, stored_len as usize)?;
+                &self.stored
+            }
+            Some(apply) => {
+                if u64::from(stored_len) > max_stored_len(self.compression, uncompressed_len, true)
+                {
+                    return Err(SnapshotError::Corrupt("transformed ram block too large"));
+                }
+                r.read_exact_into_vec(&mut self.stored, stored_len as usize)?;
+                self.plain.clear();
+                apply(&self.stored, &mut self.plain);
+                let plain_len: u32 = self
+                    .plain
+                    .len()
+                    .try_into()
+                    .map_err(|_| SnapshotError::Corrupt("ram transform output too large"))?;
+                validate_compressed_len(self.compression, uncompressed_len, plain_len)?;
+                &self.plain
+            }
+        };
+
+        match self.compression {
+            Compression::None => Ok(encoded),
+            Compression::Lz4 => {
+                let len = uncompressed_len as usize;
+                if self.decompressed.len() < len {
+                    self.decompressed
+                        .try_reserve_exact(len - self.decompressed.len())
+                        .map_err(|_| SnapshotError::OutOfMemory { len })?;
+                    self.decompressed.resize(len, 0);
+                }
+                let out = &mut self.decompressed[..len];
+                lz4_decompress_into(encoded, out)?;
+                Ok(out)
+            }
+        }
     }
 }
 
 fn decode_full<R: Read>(
     r: &mut R,
     total_len: u64,
-    compression: Compression,
+    decoder: &mut BlockDecoder<'_>,
     mut write_ram: impl FnMut(u64, &[u8]) -> Result<()>,
 ) -> Result<()> {
     let chunk_size = r.read_u32_le()?;
@@ -272,34 +592,31 @@ fn decode_full<R: Read>(
 
     let chunk_size_u64 = chunk_size as u64;
     let mut offset = 0u64;
-    let mut compressed = Vec::new();
-    let mut decompressed = Vec::new();
-    if compression == Compression::Lz4 {
-        let chunk_size = chunk_size as usize;
-        decompressed
-            .try_reserve_exact(chunk_size)
-            .map_err(|_| SnapshotError::OutOfMemory { len: chunk_size })?;
-        decompressed.resize(chunk_size, 0);
-    }
     while offset < total_len {
-        let expected_uncompressed = (total_len - offset).min(chunk_size_u64) as u32;
+        let remaining = total_len - offset;
+        if decoder.read_is_zero_record(r)? {
+            let len = r.read_u64_le()?;
+            if len == 0 || len > remaining {
+                return Err(SnapshotError::Corrupt("invalid zero-page run length"));
+            }
+            write_zeroes(&mut write_ram, offset, len)?;
+            offset += len;
+            continue;
+        }
+
+        let max_uncompressed = remaining.min(chunk_size_u64) as u32;
         let uncompressed_len = r.read_u32_le()?;
-        if uncompressed_len != expected_uncompressed {
+        // Zero-page runs split chunks into shorter data blocks.
+        let valid_len = if decoder.zero_pages {
+            uncompressed_len != 0 && uncompressed_len <= max_uncompressed
+        } else {
+            uncompressed_len == max_uncompressed
+        };
+        if !valid_len {
             return Err(SnapshotError::Corrupt("chunk uncompressed length mismatch"));
         }
-        let compressed_len = r.read_u32_le()?;
-        validate_compressed_len(compression, uncompressed_len, compressed_len)?;
-        r.read_exact_into_vec(&mut compressed, compressed_len as usize)?;
-        match compression {
-            Compression::None => {
-                write_ram(offset, &compressed)?;
-            }
-            Compression::Lz4 => {
-                let out = &mut decompressed[..uncompressed_len as usize];
-                lz4_decompress_into(&compressed, out)?;
-                write_ram(offset, out)?;
-            }
-        }
+        let data = decoder.decode(r, uncompressed_len)?;
+        write_ram(offset, data)?;
         offset += uncompressed_len as u64;
     }
     Ok(())
@@ -309,7 +626,7 @@ fn decode_dirty<R: Read>(
     r: &mut R,
     total_len: u64,
     page_size: u32,
-    compression: Compression,
+    decoder: &mut BlockDecoder<'_>,
     mut write_ram: impl FnMut(u64, &[u8]) -> Result<()>,
 ) -> Result<()> {
     let page_size_u64 = page_size as u64;
@@ -322,15 +639,6 @@ fn decode_dirty<R: Read>(
         return Err(SnapshotError::Corrupt("too many dirty pages"));
     }
 
-    let mut compressed = Vec::new();
-    let mut decompressed = Vec::new();
-    if compression == Compression::Lz4 {
-        let page_size = page_size as usize;
-        decompressed
-            .try_reserve_exact(page_size)
-            .map_err(|_| SnapshotError::OutOfMemory { len: page_size })?;
-        decompressed.resize(page_size, 0);
-    }
     let mut prev_page_idx: Option<u64> = None;
     for _ in 0..count {
         let page_idx = r.read_u64_le()?;
@@ -349,6 +657,7 @@ fn decode_dirty<R: Read>(
             return Err(SnapshotError::Corrupt("dirty page out of range"));
         }
 
+        let is_zero = decoder.read_is_zero_record(r)?;
         let expected_uncompressed = (total_len - offset).min(page_size_u64) as u32;
         let uncompressed_len = r.read_u32_le()?;
         if uncompressed_len != expected_uncompressed {
@@ -356,19 +665,27 @@ fn decode_dirty<R: Read>(
                 "dirty page uncompressed length mismatch",
             ));
         }
-        let compressed_len = r.read_u32_le()?;
-        validate_compressed_len(compression, uncompressed_len, compressed_len)?;
-        r.read_exact_into_vec(&mut compressed, compressed_len as usize)?;
-        match compression {
-            Compression::None => {
-                write_ram(offset, &compressed)?;
-            }
-            Compression::Lz4 => {
-                let out = &mut decompressed[..uncompressed_len as usize];
-                lz4_decompress_into(&compressed, out)?;
-                write_ram(offset, out)?;
-            }
+        if is_zero {
+            write_zeroes(&mut write_ram, offset, u64::from(uncompressed_len))?;
+            continue;
         }
+        let data = decoder.decode(r, uncompressed_len)?;
+        write_ram(offset, data)?;
+    }
+    Ok(())
+}
+
+/// Restore a zero-page run by writing from a shared zero buffer, without allocating.
+fn write_zeroes(
+    write_ram: &mut impl FnMut(u64, &[u8]) -> Result<()>,
+    mut offset: u64,
+    len: u64,
+) -> Result<()> {
+    let end = offset + len;
+    while offset < end {
+        let n = (end - offset).min(ZERO_FILL_LEN as u64) as usize;
+        write_ram(offset, &ZERO_FILL[..n])?;
+        offset += n as u64;
     }
     Ok(())
 }
@@ -433,17 +750,24 @@ mod tests {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 64 * 1024,
+            zero_pages: false,
         };
 
         let mut encoded = Vec::new();
-        encode_ram_section(&mut encoded, ram.len() as u64, opts, None, |offset, buf| {
-            read_from_vec(&ram, offset, buf)
-        })?;
+        encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            None,
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
 
         let mut decoded = vec![0u8; ram.len()];
         decode_ram_section_into(
             &mut std::io::Cursor::new(&encoded),
             ram.len() as u64,
+            None,
             |offset, data| write_into_vec(&mut decoded, offset, data),
         )?;
 
@@ -459,17 +783,24 @@ mod tests {
             compression: Compression::Lz4,
             page_size: 4096,
             chunk_size: 64 * 1024,
+            zero_pages: false,
         };
 
         let mut encoded = Vec::new();
-        encode_ram_section(&mut encoded, ram.len() as u64, opts, None, |offset, buf| {
-            read_from_vec(&ram, offset, buf)
-        })?;
+        encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            None,
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
 
         let mut decoded = vec![0u8; ram.len()];
         decode_ram_section_into(
             &mut std::io::Cursor::new(&encoded),
             ram.len() as u64,
+            None,
             |offset, data| write_into_vec(&mut decoded, offset, data),
         )?;
 
@@ -500,6 +831,7 @@ mod tests {
             compression,
             page_size,
             chunk_size: 1024 * 1024,
+            zero_pages: false,
         };
 
         let mut encoded = Vec::new();
@@ -508,6 +840,7 @@ mod tests {
             ram_len as u64,
             opts,
             Some(&dirty_pages),
+            None,
             |offset, buf| read_from_vec(&updated, offset, buf),
         )?;
 
@@ -515,6 +848,7 @@ mod tests {
         decode_ram_section_into(
             &mut std::io::Cursor::new(&encoded),
             ram_len as u64,
+            None,
             |offset, data| write_into_vec(&mut decoded, offset, data),
         )?;
 
@@ -532,6 +866,191 @@ mod tests {
         dirty_roundtrip(Compression::Lz4)
     }
 
+    fn xor_cipher(key: u8) -> impl FnMut(&[u8], &mut Vec<u8>) {
+        move |input, out| out.extend(input.iter().map(|b| b ^ key))
+    }
+
+    fn transformed_roundtrip(mode: RamMode, compression: Compression) -> Result<()> {
+        let ram = make_deterministic_ram(300_001);
+        let opts = RamWriteOptions {
+            mode,
+            compression,
+            page_size: 4096,
+            chunk_size: 64 * 1024,
+            zero_pages: false,
+        };
+        let dirty_pages = [0u64, 5, 70];
+
+        let mut max_block = 0usize;
+        let mut cipher = xor_cipher(0x5A);
+        let mut encrypt = |input: &[u8], out: &mut Vec<u8>| {
+            max_block = max_block.max(input.len());
+            cipher(input, out);
+        };
+        let mut encoded = Vec::new();
+        encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            Some(&dirty_pages),
+            Some(RamTransform {
+                metadata: b"nonce",
+                apply: &mut encrypt,
+            }),
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        let block_len = match mode {
+            RamMode::Full => opts.chunk_size,
+            RamMode::Dirty => opts.page_size,
+        };
+        assert!(max_block as u64 <= max_stored_len(compression, block_len, false));
+
+        let mut decoded = vec![0u8; ram.len()];
+        let mut decrypt = xor_cipher(0x5A);
+        decode_ram_section_into(
+            &mut std::io::Cursor::new(&encoded),
+            ram.len() as u64,
+            Some(&mut decrypt),
+            |offset, data| write_into_vec(&mut decoded, offset, data),
+        )?;
+
+        match mode {
+            RamMode::Full => assert_eq!(decoded, ram),
+            RamMode::Dirty => {
+                for &page in &dirty_pages {
+                    let range = page as usize * 4096..(page as usize + 1) * 4096;
+                    assert_eq!(decoded[range.clone()], ram[range]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn transformed_full_roundtrip() -> Result<()> {
+        transformed_roundtrip(RamMode::Full, Compression::None)?;
+        transformed_roundtrip(RamMode::Full, Compression::Lz4)
+    }
+
+    #[test]
+    fn transformed_dirty_roundtrip() -> Result<()> {
+        transformed_roundtrip(RamMode::Dirty, Compression::None)?;
+        transformed_roundtrip(RamMode::Dirty, Compression::Lz4)
+    }
+
+    #[test]
+    fn transformed_ram_is_not_stored_in_plaintext() -> Result<()> {
+        let ram = vec![0x42u8; 16 * 1024];
+        let opts = RamWriteOptions {
+            compression: Compression::None,
+            chunk_size: 4096,
+            ..Default::default()
+        };
+        let mut encrypt = xor_cipher(0xFF);
+        let mut encoded = Vec::new();
+        encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            None,
+            Some(RamTransform {
+                metadata: &[],
+                apply: &mut encrypt,
+            }),
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        assert!(!encoded.windows(64).any(|w| w.iter().all(|&b| b == 0x42)));
+        Ok(())
+    }
+
+    #[test]
+    fn transform_mismatch_is_rejected() -> Result<()> {
+        let ram = make_deterministic_ram(8192);
+        let opts = RamWriteOptions::default();
+
+        let mut plain = Vec::new();
+        encode_ram_section(
+            &mut plain,
+            ram.len() as u64,
+            opts,
+            None,
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        let mut decrypt = xor_cipher(1);
+        let err = decode_ram_section_into(
+            &mut std::io::Cursor::new(&plain),
+            ram.len() as u64,
+            Some(&mut decrypt),
+            |_, _| Ok(()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SnapshotError::RamTransformMismatch {
+                transformed: false,
+                supplied: true
+            }
+        ));
+
+        let mut encrypt = xor_cipher(1);
+        let mut transformed = Vec::new();
+        encode_ram_section(
+            &mut transformed,
+            ram.len() as u64,
+            opts,
+            None,
+            Some(RamTransform {
+                metadata: &[],
+                apply: &mut encrypt,
+            }),
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        let err = decode_ram_section_into(
+            &mut std::io::Cursor::new(&transformed),
+            ram.len() as u64,
+            None,
+            |_, _| Ok(()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SnapshotError::RamTransformMismatch {
+                transformed: true,
+                supplied: false
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_transform_output_is_rejected() {
+        let ram = vec![0x11u8; 4096];
+        let mut pad = |input: &[u8], out: &mut Vec<u8>| {
+            out.extend_from_slice(input);
+            out.resize(
+                out.len() + crate::limits::MAX_RAM_TRANSFORM_OVERHEAD as usize + 1,
+                0,
+            );
+        };
+        let err = encode_ram_section(
+            &mut Vec::new(),
+            ram.len() as u64,
+            RamWriteOptions {
+                compression: Compression::None,
+                ..Default::default()
+            },
+            None,
+            Some(RamTransform {
+                metadata: &[],
+                apply: &mut pad,
+            }),
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::Corrupt(_)));
+    }
+
     #[test]
     fn lz4_compress_into_matches_allocating_api() {
         let input = make_deterministic_ram(256 * 1024 + 3);
@@ -542,4 +1061,170 @@ mod tests {
         let written = lz4_flex::block::compress_into(&input, &mut out).unwrap();
         assert_eq!(&out[..written], expected);
     }
+
+    /// RAM where every page whose index is a multiple of `data_every` holds random data and all
+    /// other pages are zero.
+    fn sparse_ram(len: usize, data_every: usize) -> Vec<u8> {
+        let mut ram = vec![0u8; len];
+        let random = make_deterministic_ram(len);
+        for (idx, page) in ram.chunks_mut(4096).enumerate() {
+            if idx % data_every == 0 {
+                let start = idx * 4096;
+                page.copy_from_slice(&random[start..start + page.len()]);
+            }
+        }
+        ram
+    }
+
+    fn zero_pages_full_roundtrip(compression: Compression) -> Result<()> {
+        // Not page- or chunk-aligned, so runs cross chunk boundaries and the last page is partial.
+        let ram = sparse_ram(1_000_123, 3);
+        let opts = RamWriteOptions {
+            compression,
+            chunk_size: 64 * 1024 + 512,
+            ..Default::default()
+        };
+
+        let mut encoded = Vec::new();
+        let stats = encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            None,
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        assert_eq!(stats.raw_len, ram.len() as u64);
+        assert_eq!(stats.stored_len, encoded.len() as u64);
+        assert!(stats.zero_len > 0);
+
+        // Pre-fill so restored zero pages must actually be written.
+        let mut decoded = vec![0xEEu8; ram.len()];
+        decode_ram_section_into(
+            &mut std::io::Cursor::new(&encoded),
+            ram.len() as u64,
+            None,
+            |offset, data| write_into_vec(&mut decoded, offset, data),
+        )?;
+        assert_eq!(decoded, ram);
+        Ok(())
+    }
+
+    #[test]
+    fn zero_pages_full_roundtrip_none() -> Result<()> {
+        zero_pages_full_roundtrip(Compression::None)
+    }
+
+    #[test]
+    fn zero_pages_full_roundtrip_lz4() -> Result<()> {
+        zero_pages_full_roundtrip(Compression::Lz4)
+    }
+
+    #[test]
+    fn zero_pages_dirty_roundtrip() -> Result<()> {
+        let ram = sparse_ram(4096 * 8 + 100, 2);
+        let opts = RamWriteOptions {
+            mode: RamMode::Dirty,
+            ..Default::default()
+        };
+        // Pages 0, 2 and 8 hold data; pages 1 and 3 are zero.
+        let dirty_pages = [0u64, 1, 2, 3, 8];
+
+        let mut encoded = Vec::new();
+        let stats = encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            opts,
+            Some(&dirty_pages),
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        assert_eq!(stats.raw_len, 4 * 4096 + 100);
+        assert_eq!(stats.zero_len, 2 * 4096);
+
+        let mut decoded = vec![0xEEu8; ram.len()];
+        decode_ram_section_into(
+            &mut std::io::Cursor::new(&encoded),
+            ram.len() as u64,
+            None,
+            |offset, data| write_into_vec(&mut decoded, offset, data),
+        )?;
+        assert_eq!(decoded[..4 * 4096], ram[..4 * 4096]);
+        assert_eq!(decoded[8 * 4096..], ram[8 * 4096..]);
+        assert!(decoded[4 * 4096..8 * 4096].iter().all(|&b| b == 0xEE));
+        Ok(())
+    }
+
+    #[test]
+    fn zero_run_past_end_is_rejected() -> Result<()> {
+        let ram = vec![0u8; 8192];
+        let mut encoded = Vec::new();
+        encode_ram_section(
+            &mut encoded,
+            ram.len() as u64,
+            RamWriteOptions::default(),
+            None,
+            None,
+            |offset, buf| read_from_vec(&ram, offset, buf),
+        )?;
+        // Header (16) + chunk_size (4) + a single zero-run record: kind (1) + len (8).
+        assert_eq!(encoded.len(), 16 + 4 + 9);
+        encoded[21..29].copy_from_slice(&8193u64.to_le_bytes());
+
+        let err = decode_ram_section_into(
+            &mut std::io::Cursor::new(&encoded),
+            ram.len() as u64,
+            None,
+            |_, _| Ok(()),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SnapshotError::Corrupt("invalid zero-page run length")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn zero_pages_shrink_mostly_idle_ram() -> Result<()> {
+        // Typical idle guest: roughly one page in sixteen holds (incompressible) data.
+        let ram = sparse_ram(32 * 1024 * 1024, 16);
+        let encode = |zero_pages: bool| -> Result<RamSectionStats> {
+            let opts = RamWriteOptions {
+                zero_pages,
+                ..Default::default()
+            };
+            encode_ram_section(
+                &mut std::io::sink(),
+                ram.len() as u64,
+                opts,
+                None,
+                None,
+                |offset, buf| read_from_vec(&ram, offset, buf),
+            )
+        };
+
+        let stats = encode(true)?;
+        assert_eq!(stats.zero_len, ram.len() as u64 / 16 * 15);
+        let ratio = stats.raw_len / stats.stored_len;
+        assert!(ratio >= 12, "compression ratio {ratio} too low");
+
+        // Zero-page runs beat compressing the zero pages with lz4.
+        let without = encode(false)?;
+        assert!(stats.stored_len < without.stored_len);
+
+        // A fully idle guest stores RAM in a few bytes.
+        let idle = vec![0u8; 32 * 1024 * 1024];
+        let mut encoded = Vec::new();
+        let stats = encode_ram_section(
+            &mut encoded,
+            idle.len() as u64,
+            RamWriteOptions::default(),
+            None,
+            None,
+            |offset, buf| read_from_vec(&idle, offset, buf),
+        )?;
+        assert_eq!(stats.stored_len, 16 + 4 + 9);
+        Ok(())
+    }
 }
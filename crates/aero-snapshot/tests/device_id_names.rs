@@ -62,6 +62,12 @@ fn platform_device_ids_have_stable_names_and_numbers() {
         (DeviceId::VIRTIO_INPUT_MOUSE, 27u32, "VIRTIO_INPUT_MOUSE"),
         (DeviceId::GPU_VRAM, 28u32, "GPU_VRAM"),
         (DeviceId::VIRTIO_INPUT_TABLET, 29u32, "VIRTIO_INPUT_TABLET"),
+        (DeviceId::FDC, 30u32, "FDC"),
+        (DeviceId::DMA, 31u32, "DMA"),
+        (DeviceId::VIRTIO_BALLOON, 32u32, "VIRTIO_BALLOON"),
+        (DeviceId::TPM, 33u32, "TPM"),
+        (DeviceId::VIRTIO_9P, 34u32, "VIRTIO_9P"),
+        (DeviceId::VIRTIO_RNG, 35u32, "VIRTIO_RNG"),
     ];
 
     for (id, expected_num, expected_name) in cases {
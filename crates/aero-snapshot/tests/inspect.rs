@@ -3,8 +3,9 @@
 use std::io::{Cursor, Read, Seek};
 
 use aero_snapshot::{
-    inspect_snapshot, save_snapshot, Compression, CpuState, DiskOverlayRefs, MmuState, RamMode,
-    RamWriteOptions, Result, SaveOptions, SnapshotError, SnapshotMeta, SnapshotSource,
+    inspect_snapshot, save_snapshot, save_snapshot_with_ram_transform, Compression, CpuState,
+    DiskOverlayRefs, MmuState, RamMode, RamWriteOptions, Result, SaveOptions, SectionId,
+    SnapshotError, SnapshotMeta, SnapshotSource,
 };
 
 struct DummySource {
@@ -109,6 +110,7 @@ fn inspect_reads_only_headers() -> Result<()> {
             compression: Compression::None,
             page_size: 4096,
             chunk_size,
+            zero_pages: false,
         },
     };
 
@@ -131,6 +133,7 @@ fn inspect_reads_only_headers() -> Result<()> {
     assert_eq!(ram.compression, Compression::None);
     assert_eq!(ram.chunk_size, Some(chunk_size));
     assert_eq!(ram.dirty_count, None);
+    assert_eq!(ram.transform_metadata, None);
 
     assert!(
         reader.bytes_read() < 4096,
@@ -140,3 +143,38 @@ fn inspect_reads_only_headers() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn inspect_reports_ram_transform_metadata() -> Result<()> {
+    let mut source = DummySource {
+        meta: SnapshotMeta::default(),
+        ram_len: 64 * 1024,
+    };
+
+    let mut buf = Cursor::new(Vec::new());
+    let mut identity = |input: &[u8], out: &mut Vec<u8>| out.extend_from_slice(input);
+    save_snapshot_with_ram_transform(
+        &mut buf,
+        &mut source,
+        SaveOptions::default(),
+        b"salt-and-nonce",
+        &mut identity,
+    )?;
+    buf.set_position(0);
+    let index = inspect_snapshot(&mut buf)?;
+
+    let ram = index.ram.expect("expected RAM section");
+    assert_eq!(ram.total_len, 64 * 1024);
+    assert_eq!(ram.chunk_size, Some(1024 * 1024));
+    assert_eq!(
+        ram.transform_metadata.as_deref(),
+        Some(&b"salt-and-nonce"[..])
+    );
+    let ram_section = index
+        .sections
+        .iter()
+        .find(|section| section.id == SectionId::RAM)
+        .expect("expected RAM section header");
+    assert_eq!(ram_section.version, 2);
+    Ok(())
+}
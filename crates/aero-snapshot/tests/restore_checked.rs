@@ -358,6 +358,7 @@ fn restore_snapshot_checked_respects_custom_dirty_page_size() {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 1024,
+            zero_pages: false,
         },
     };
     let bytes = snapshot_bytes(&mut source, opts).unwrap();
@@ -219,34 +219,29 @@ impl From<Box<dyn VirtualDisk>> for AeroVirtualDiskAsDeviceBackend {
 }
 
 fn map_aero_storage_error_to_io(err: aero_storage::DiskError) -> io::Error {
-    match err {
-        err @ (aero_storage::DiskError::UnalignedLength { .. }
+    // The kind follows the underlying cause; the whole error (with its layer context) is kept as
+    // the payload.
+    let kind = match err.root() {
+        aero_storage::DiskError::UnalignedLength { .. }
         | aero_storage::DiskError::OffsetOverflow
         | aero_storage::DiskError::InvalidConfig(_)
-        | aero_storage::DiskError::InvalidSparseHeader(_)) => {
-            io::Error::new(io::ErrorKind::InvalidInput, err)
+        | aero_storage::DiskError::InvalidSparseHeader(_) => io::ErrorKind::InvalidInput,
+        aero_storage::DiskError::CorruptImage(_)
+        | aero_storage::DiskError::CorruptSparseImage(_)
+        | aero_storage::DiskError::ChecksumMismatch { .. } => io::ErrorKind::InvalidData,
+        aero_storage::DiskError::OutOfBounds { .. } => io::ErrorKind::UnexpectedEof,
+        aero_storage::DiskError::Unsupported(_) | aero_storage::DiskError::NotSupported(_) => {
+            io::ErrorKind::Unsupported
         }
-        err @ (aero_storage::DiskError::CorruptImage(_)
-        | aero_storage::DiskError::CorruptSparseImage(_)) => {
-            io::Error::new(io::ErrorKind::InvalidData, err)
-        }
-        err @ aero_storage::DiskError::OutOfBounds { .. } => {
-            io::Error::new(io::ErrorKind::UnexpectedEof, err)
-        }
-        err @ (aero_storage::DiskError::Unsupported(_)
-        | aero_storage::DiskError::NotSupported(_)) => {
-            io::Error::new(io::ErrorKind::Unsupported, err)
-        }
-        err @ aero_storage::DiskError::QuotaExceeded => {
-            io::Error::new(io::ErrorKind::StorageFull, err)
-        }
-        err @ aero_storage::DiskError::InUse => io::Error::new(io::ErrorKind::ResourceBusy, err),
-        err @ aero_storage::DiskError::InvalidState(_) => io::Error::other(err),
-        err @ aero_storage::DiskError::BackendUnavailable => {
-            io::Error::new(io::ErrorKind::NotConnected, err)
-        }
-        err @ aero_storage::DiskError::Io(_) => io::Error::other(err),
-    }
+        aero_storage::DiskError::QuotaExceeded => io::ErrorKind::StorageFull,
+        aero_storage::DiskError::InUse => io::ErrorKind::ResourceBusy,
+        aero_storage::DiskError::BackendUnavailable => io::ErrorKind::NotConnected,
+        aero_storage::DiskError::NativeIo { source, .. } => source.kind(),
+        aero_storage::DiskError::InvalidState(_)
+        | aero_storage::DiskError::Io(_)
+        | aero_storage::DiskError::Context { .. } => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, err)
 }
 
 #[cfg(test)]
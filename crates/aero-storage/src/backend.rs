@@ -1,5 +1,7 @@
 use crate::{DiskError, Result};
 
+use std::sync::{Arc, OnceLock};
+
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs::{File, OpenOptions};
 #[cfg(not(target_arch = "wasm32"))]
@@ -26,7 +28,8 @@ use std::os::windows::fs::FileExt;
 /// `aero_storage::VirtualDisk` and the Rust device/controller stack (e.g. AHCI/IDE).
 ///
 /// Errors are reported via [`DiskError`]. In particular, [`DiskError::Io`] stores a plain
-/// `String` so wasm32 implementations can propagate errors originating from JavaScript/DOM APIs.
+/// `String` so wasm32 implementations can propagate errors originating from JavaScript/DOM APIs;
+/// native file backends return [`DiskError::NativeIo`] to keep the `std::io::Error`.
 ///
 /// Browser IndexedDB APIs are Promise-based (async) and therefore cannot implement this trait
 /// safely in the *same* Worker thread. Supporting IndexedDB would require an explicit split
@@ -150,27 +153,170 @@ impl<B: StorageBackend> StorageBackend for ReadOnlyBackend<B> {
     }
 }
 
+/// Granularity of copy-on-write sharing between forked [`MemBackend`]s.
+pub const MEM_BACKEND_PAGE_SIZE: usize = 4096;
+
+type MemPage = [u8; MEM_BACKEND_PAGE_SIZE];
+
+/// Page table shared between forks. Pages are reference-counted and copied on first write; `None`
+/// pages read as zeros.
+#[derive(Clone, Debug, Default)]
+struct MemPages {
+    pages: Vec<Option<Arc<MemPage>>>,
+    len: usize,
+}
+
+impl MemPages {
+    fn from_slice(data: &[u8]) -> Result<Self> {
+        let mut pages = Vec::new();
+        pages
+            .try_reserve_exact(data.len().div_ceil(MEM_BACKEND_PAGE_SIZE))
+            .map_err(|_| DiskError::QuotaExceeded)?;
+        for chunk in data.chunks(MEM_BACKEND_PAGE_SIZE) {
+            if chunk.iter().all(|&b| b == 0) {
+                pages.push(None);
+                continue;
+            }
+            let mut page = [0u8; MEM_BACKEND_PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            pages.push(Some(Arc::new(page)));
+        }
+        Ok(Self {
+            pages,
+            len: data.len(),
+        })
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        let mut out = vec![0u8; self.len];
+        for (chunk, page) in out.chunks_mut(MEM_BACKEND_PAGE_SIZE).zip(&self.pages) {
+            if let Some(page) = page {
+                chunk.copy_from_slice(&page[..chunk.len()]);
+            }
+        }
+        out
+    }
+
+    fn set_len(&mut self, len: usize) -> Result<()> {
+        let page_count = len.div_ceil(MEM_BACKEND_PAGE_SIZE);
+        if page_count > self.pages.len() {
+            self.pages
+                .try_reserve_exact(page_count - self.pages.len())
+                .map_err(|_| DiskError::QuotaExceeded)?;
+        }
+        // Bytes dropped from a partial last page must read as zeros if the backend grows again.
+        if len < self.len && !len.is_multiple_of(MEM_BACKEND_PAGE_SIZE) {
+            if let Some(page) = &mut self.pages[len / MEM_BACKEND_PAGE_SIZE] {
+                Arc::make_mut(page)[len % MEM_BACKEND_PAGE_SIZE..].fill(0);
+            }
+        }
+        self.pages.resize(page_count, None);
+        self.len = len;
+        Ok(())
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        let mut pos = offset;
+        for chunk in split_at_pages(offset, buf.len()) {
+            let dst = &mut buf[pos - offset..pos - offset + chunk.len];
+            match &self.pages[chunk.page] {
+                Some(page) => dst.copy_from_slice(&page[chunk.start..chunk.start + chunk.len]),
+                None => dst.fill(0),
+            }
+            pos += chunk.len;
+        }
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) {
+        let mut pos = offset;
+        for chunk in split_at_pages(offset, buf.len()) {
+            let page =
+                self.pages[chunk.page].get_or_insert_with(|| Arc::new([0; MEM_BACKEND_PAGE_SIZE]));
+            Arc::make_mut(page)[chunk.start..chunk.start + chunk.len]
+                .copy_from_slice(&buf[pos - offset..pos - offset + chunk.len]);
+            pos += chunk.len;
+        }
+    }
+}
+
+struct PageChunk {
+    page: usize,
+    start: usize,
+    len: usize,
+}
+
+/// Split the byte range `offset..offset + len` at page boundaries.
+fn split_at_pages(offset: usize, len: usize) -> impl Iterator<Item = PageChunk> {
+    let end = offset + len;
+    let mut pos = offset;
+    std::iter::from_fn(move || {
+        if pos >= end {
+            return None;
+        }
+        let start = pos % MEM_BACKEND_PAGE_SIZE;
+        let chunk = PageChunk {
+            page: pos / MEM_BACKEND_PAGE_SIZE,
+            start,
+            len: (MEM_BACKEND_PAGE_SIZE - start).min(end - pos),
+        };
+        pos += chunk.len;
+        Some(chunk)
+    })
+}
+
+#[derive(Clone, Debug)]
+enum MemData {
+    Flat(Vec<u8>),
+    /// Copy-on-write pages, used once the backend has been forked. `flat` caches a contiguous copy
+    /// for [`MemBackend::as_slice`] until the next mutation.
+    Paged {
+        pages: MemPages,
+        flat: OnceLock<Vec<u8>>,
+    },
+}
+
+impl Default for MemData {
+    fn default() -> Self {
+        MemData::Flat(Vec::new())
+    }
+}
+
 /// In-memory storage backend used for tests and benchmarks.
+///
+/// A backend starts out as one contiguous buffer. [`MemBackend::fork`] switches it to
+/// [`MEM_BACKEND_PAGE_SIZE`] pages shared copy-on-write with the fork, so stamping out many
+/// mutated copies of one template image only costs the pages each copy writes. Reads of shared
+/// pages do not allocate. [`MemBackend::freeze`] turns a backend into an immutable
+/// [`FrozenBackend`] that any number of disks can read from without copying.
 #[derive(Clone, Debug, Default)]
 pub struct MemBackend {
-    data: Vec<u8>,
+    data: MemData,
 }
 
 impl MemBackend {
     pub fn new() -> Self {
-        Self { data: Vec::new() }
+        Self {
+            data: MemData::Flat(Vec::new()),
+        }
     }
 
     /// Construct a `MemBackend` from an existing byte vector without copying.
     #[must_use]
     pub fn from_vec(data: Vec<u8>) -> Self {
-        Self { data }
+        Self {
+            data: MemData::Flat(data),
+        }
     }
 
     /// Consume the backend and return the underlying byte vector.
+    ///
+    /// This copies the contents out of a forked backend's shared pages.
     #[must_use]
     pub fn into_vec(self) -> Vec<u8> {
-        self.data
+        match self.data {
+            MemData::Flat(data) => data,
+            MemData::Paged { pages, flat } => flat.into_inner().unwrap_or_else(|| pages.to_vec()),
+        }
     }
 
     pub fn with_len(len: u64) -> Result<Self> {
@@ -179,28 +325,88 @@ impl MemBackend {
         data.try_reserve_exact(len_usize)
             .map_err(|_| DiskError::QuotaExceeded)?;
         data.resize(len_usize, 0);
-        Ok(Self { data })
+        Ok(Self {
+            data: MemData::Flat(data),
+        })
     }
 
+    /// The backend contents as one contiguous slice.
+    ///
+    /// A forked backend materializes (and caches until the next write) a private copy, so prefer
+    /// [`StorageBackend::read_at`] for large forked images.
     pub fn as_slice(&self) -> &[u8] {
-        &self.data
+        match &self.data {
+            MemData::Flat(data) => data,
+            MemData::Paged { pages, flat } => flat.get_or_init(|| pages.to_vec()),
+        }
+    }
+
+    /// Create a copy-on-write copy of this backend.
+    ///
+    /// Both backends share all pages; whichever side writes a shared page first gets its own copy
+    /// of it. Forking a contiguous backend converts it to pages first (all-zero pages are not
+    /// stored).
+    pub fn fork(&mut self) -> Result<MemBackend> {
+        let pages = self.pages_mut()?.clone();
+        Ok(MemBackend {
+            data: MemData::Paged {
+                pages,
+                flat: OnceLock::new(),
+            },
+        })
+    }
+
+    /// Turn this backend into an immutable base that can be shared by any number of disks (e.g.
+    /// [`crate::AeroCowDisk`] overlays) without copying.
+    pub fn freeze(mut self) -> Result<FrozenBackend> {
+        let pages = std::mem::take(self.pages_mut()?);
+        Ok(FrozenBackend {
+            pages: Arc::new(pages),
+        })
+    }
+
+    fn as_len(&self) -> usize {
+        match &self.data {
+            MemData::Flat(data) => data.len(),
+            MemData::Paged { pages, .. } => pages.len,
+        }
+    }
+
+    /// Switch to the paged representation (if needed) and return the pages for mutation.
+    fn pages_mut(&mut self) -> Result<&mut MemPages> {
+        if let MemData::Flat(data) = &self.data {
+            self.data = MemData::Paged {
+                pages: MemPages::from_slice(data)?,
+                flat: OnceLock::new(),
+            };
+        }
+        match &mut self.data {
+            MemData::Paged { pages, flat } => {
+                *flat = OnceLock::new();
+                Ok(pages)
+            }
+            MemData::Flat(_) => unreachable!(),
+        }
     }
 }
 
 impl StorageBackend for MemBackend {
     fn len(&mut self) -> Result<u64> {
-        Ok(self.data.len() as u64)
+        Ok(self.as_len() as u64)
     }
 
     fn set_len(&mut self, len: u64) -> Result<()> {
         let len_usize: usize = len.try_into().map_err(|_| DiskError::OffsetOverflow)?;
-        let cur_len = self.data.len();
+        let data = match &mut self.data {
+            MemData::Flat(data) => data,
+            MemData::Paged { .. } => return self.pages_mut()?.set_len(len_usize),
+        };
+        let cur_len = data.len();
         if len_usize > cur_len {
-            self.data
-                .try_reserve_exact(len_usize - cur_len)
+            data.try_reserve_exact(len_usize - cur_len)
                 .map_err(|_| DiskError::QuotaExceeded)?;
         }
-        self.data.resize(len_usize, 0);
+        data.resize(len_usize, 0);
         Ok(())
     }
 
@@ -209,14 +415,18 @@ impl StorageBackend for MemBackend {
         let end = offset_usize
             .checked_add(buf.len())
             .ok_or(DiskError::OffsetOverflow)?;
-        if end > self.data.len() {
+        let len = self.as_len();
+        if end > len {
             return Err(DiskError::OutOfBounds {
                 offset,
                 len: buf.len(),
-                capacity: self.data.len() as u64,
+                capacity: len as u64,
             });
         }
-        buf.copy_from_slice(&self.data[offset_usize..end]);
+        match &self.data {
+            MemData::Flat(data) => buf.copy_from_slice(&data[offset_usize..end]),
+            MemData::Paged { pages, .. } => pages.read(offset_usize, buf),
+        }
         Ok(())
     }
 
@@ -225,11 +435,14 @@ impl StorageBackend for MemBackend {
         let end = offset_usize
             .checked_add(buf.len())
             .ok_or(DiskError::OffsetOverflow)?;
-        if end > self.data.len() {
+        if end > self.as_len() {
             let end_u64 = u64::try_from(end).map_err(|_| DiskError::OffsetOverflow)?;
             self.set_len(end_u64)?;
         }
-        self.data[offset_usize..end].copy_from_slice(buf);
+        match &mut self.data {
+            MemData::Flat(data) => data[offset_usize..end].copy_from_slice(buf),
+            MemData::Paged { .. } => self.pages_mut()?.write(offset_usize, buf),
+        }
         Ok(())
     }
 
@@ -238,6 +451,62 @@ impl StorageBackend for MemBackend {
     }
 }
 
+/// Immutable, cheaply clonable in-memory backend produced by [`MemBackend::freeze`].
+///
+/// Clones share the same pages. Writes and resizes fail with [`DiskError::NotSupported`]; use
+/// [`FrozenBackend::fork`] for a writable copy-on-write copy.
+#[derive(Clone, Debug)]
+pub struct FrozenBackend {
+    pages: Arc<MemPages>,
+}
+
+impl FrozenBackend {
+    /// Create a writable [`MemBackend`] sharing this backend's pages copy-on-write.
+    #[must_use]
+    pub fn fork(&self) -> MemBackend {
+        MemBackend {
+            data: MemData::Paged {
+                pages: (*self.pages).clone(),
+                flat: OnceLock::new(),
+            },
+        }
+    }
+}
+
+impl StorageBackend for FrozenBackend {
+    fn len(&mut self) -> Result<u64> {
+        Ok(self.pages.len as u64)
+    }
+
+    fn set_len(&mut self, _len: u64) -> Result<()> {
+        Err(DiskError::NotSupported("read-only".into()))
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let offset_usize: usize = offset.try_into().map_err(|_| DiskError::OffsetOverflow)?;
+        let end = offset_usize
+            .checked_add(buf.len())
+            .ok_or(DiskError::OffsetOverflow)?;
+        if end > self.pages.len {
+            return Err(DiskError::OutOfBounds {
+                offset,
+                len: buf.len(),
+                capacity: self.pages.len as u64,
+            });
+        }
+        self.pages.read(offset_usize, buf);
+        Ok(())
+    }
+
+    fn write_at(&mut self, _offset: u64, _buf: &[u8]) -> Result<()> {
+        Err(DiskError::NotSupported("read-only".into()))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
 /// Native `std::fs::File`-backed storage backend.
 ///
 /// This backend is available on non-wasm32 targets and is intended for host-side tooling
@@ -270,11 +539,14 @@ impl StdFileBackend {
             .write(!read_only)
             .open(path_ref)
             .map_err(|e| {
-                DiskError::Io(format!(
-                    "failed to open file (path={} read_only={}): {e}",
-                    path_ref.display(),
-                    read_only
-                ))
+                DiskError::native_io(
+                    format!(
+                        "failed to open file (path={} read_only={})",
+                        path_ref.display(),
+                        read_only
+                    ),
+                    e,
+                )
             })?;
         Ok(Self {
             file,
@@ -303,11 +575,14 @@ impl StdFileBackend {
             .truncate(true)
             .open(path_ref)
             .map_err(|e| {
-                DiskError::Io(format!(
-                    "failed to create file (path={} size={}): {e}",
-                    path_ref.display(),
-                    size
-                ))
+                DiskError::native_io(
+                    format!(
+                        "failed to create file (path={} size={})",
+                        path_ref.display(),
+                        size
+                    ),
+                    e,
+                )
             })?;
 
         let mut backend = Self {
@@ -325,6 +600,12 @@ impl StdFileBackend {
         self.file
     }
 
+    /// Borrow the underlying [`File`].
+    #[must_use]
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
     /// Wrap an already-open [`std::fs::File`].
     ///
     /// Note: the returned backend has no path metadata (used only for error messages).
@@ -370,16 +651,19 @@ impl StdFileBackend {
     }
 
     fn io_err(&self, op: &str, err: std::io::Error) -> DiskError {
-        DiskError::Io(format!("{op} failed (path={}): {err}", self.path_str()))
+        DiskError::native_io(format!("{op} failed (path={})", self.path_str()), err)
     }
 
     fn io_err_at(&self, op: &str, offset: u64, len: usize, err: std::io::Error) -> DiskError {
-        DiskError::Io(format!(
-            "{op} failed (path={} offset={} len={}): {err}",
-            self.path_str(),
-            offset,
-            len
-        ))
+        DiskError::native_io(
+            format!(
+                "{op} failed (path={} offset={} len={})",
+                self.path_str(),
+                offset,
+                len
+            ),
+            err,
+        )
     }
 
     #[cfg(unix)]
@@ -438,11 +722,10 @@ impl StorageBackend for StdFileBackend {
             return Err(DiskError::NotSupported("read-only backend".to_string()));
         }
         self.file.set_len(len).map_err(|e| {
-            DiskError::Io(format!(
-                "set_len failed (path={} len={}): {e}",
-                self.path_str(),
-                len
-            ))
+            DiskError::native_io(
+                format!("set_len failed (path={} len={})", self.path_str(), len),
+                e,
+            )
         })?;
         Ok(())
     }
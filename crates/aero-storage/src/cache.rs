@@ -1,5 +1,5 @@
 use crate::util::checked_range;
-use crate::{DiskError, Result, VirtualDisk};
+use crate::{DiskError, DiskLayer, Result, VirtualDisk};
 use lru::LruCache;
 use std::num::NonZeroUsize;
 
@@ -10,23 +10,53 @@ pub struct BlockCacheStats {
     pub evictions: u64,
     /// Number of dirty cached blocks successfully written back to the underlying disk.
     pub writebacks: u64,
+    /// Number of flushes issued to the underlying disk.
+    pub inner_flushes: u64,
 }
 
+/// Default for [`BlockCachedDisk::set_flush_batch_window`].
+pub const DEFAULT_FLUSH_BATCH_WINDOW: usize = 16;
+
 struct CacheEntry {
     data: Vec<u8>,
     dirty: bool,
+    /// Dirty data written before the pending write barrier; it must reach the underlying disk
+    /// (followed by an inner barrier) before any data written after the barrier.
+    ordered: bool,
 }
 
 /// A simple LRU, write-back cache in front of a [`VirtualDisk`].
 ///
 /// The cache works in fixed-size blocks (e.g. 1 MiB). This reduces the overhead of
 /// calling into browser storage APIs for many tiny sector operations.
+///
+/// # Write barriers and flush batching
+///
+/// [`VirtualDisk::write_barrier`] does not touch the underlying disk: it only marks the blocks
+/// dirtied so far as ordered. The barrier is propagated lazily, right before data written after
+/// it would reach the underlying disk, by writing back the ordered blocks and issuing an inner
+/// [`VirtualDisk::write_barrier`]. Over a disk with ordered writes (whose barrier is a no-op), a
+/// guest `write, FLUSH, write, FLUSH` sequence whose FLUSH completions are held until a final
+/// [`VirtualDisk::flush`] therefore costs a single backend flush.
+///
+/// To bound the amount of data left undurable, the barrier escalates to a full flush once
+/// [`BlockCachedDisk::set_flush_batch_window`] barriers have been coalesced. [`VirtualDisk::flush`]
+/// skips the backend flush when nothing was written to the underlying disk since the previous
+/// one.
 pub struct BlockCachedDisk<D> {
     inner: D,
     block_size: usize,
     max_cached_blocks: NonZeroUsize,
     cache: LruCache<u64, CacheEntry>,
     stats: BlockCacheStats,
+    /// Some cached blocks are `ordered` behind a barrier not yet propagated to `inner`.
+    barrier_pending: bool,
+    /// Barriers coalesced since the last backend flush.
+    batched_barriers: usize,
+    flush_batch_window: usize,
+    /// `inner` received writes since its last flush / barrier.
+    inner_unflushed: bool,
+    inner_unordered: bool,
 }
 
 impl<D: VirtualDisk> BlockCachedDisk<D> {
@@ -42,6 +72,12 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
             max_cached_blocks,
             cache: LruCache::new(max_cached_blocks),
             stats: BlockCacheStats::default(),
+            barrier_pending: false,
+            batched_barriers: 0,
+            flush_batch_window: DEFAULT_FLUSH_BATCH_WINDOW,
+            // The underlying disk's state is unknown; the first flush always reaches it.
+            inner_unflushed: true,
+            inner_unordered: true,
         })
     }
 
@@ -49,6 +85,16 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
         self.stats
     }
 
+    /// Number of write barriers coalesced before one is escalated to a full flush of the
+    /// underlying disk. `0` makes every barrier a full flush.
+    pub fn set_flush_batch_window(&mut self, barriers: usize) {
+        self.flush_batch_window = barriers;
+    }
+
+    pub fn flush_batch_window(&self) -> usize {
+        self.flush_batch_window
+    }
+
     pub fn inner(&self) -> &D {
         &self.inner
     }
@@ -63,6 +109,14 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
 
     fn ensure_space_for_block(&mut self) -> Result<()> {
         while self.cache.len() >= self.max_cached_blocks.get() {
+            // Data written after the pending barrier may only reach `inner` once the barrier has.
+            if self
+                .cache
+                .peek_lru()
+                .is_some_and(|(_, entry)| entry.dirty && !entry.ordered)
+            {
+                self.propagate_barrier()?;
+            }
             let Some((evicted_idx, evicted)) = self.cache.pop_lru() else {
                 break;
             };
@@ -91,7 +145,11 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
         data.try_reserve_exact(self.block_size)
             .map_err(|_| DiskError::QuotaExceeded)?;
         data.resize(self.block_size, 0);
-        let mut entry = CacheEntry { data, dirty: false };
+        let mut entry = CacheEntry {
+            data,
+            dirty: false,
+            ordered: false,
+        };
 
         let start = block_idx
             .checked_mul(self.block_size as u64)
@@ -99,7 +157,8 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
         if start < self.inner.capacity_bytes() {
             let max_len = (self.inner.capacity_bytes() - start).min(self.block_size as u64);
             self.inner
-                .read_at(start, &mut entry.data[..max_len as usize])?;
+                .read_at(start, &mut entry.data[..max_len as usize])
+                .map_err(|e| e.with_context(DiskLayer::BlockCache, start, max_len as usize))?;
         }
 
         self.insert_cache_entry(block_idx, entry)
@@ -124,8 +183,82 @@ impl<D: VirtualDisk> BlockCachedDisk<D> {
         }
         let max_len = (self.inner.capacity_bytes() - start).min(self.block_size as u64);
         self.inner
-            .write_at(start, &entry.data[..max_len as usize])?;
+            .write_at(start, &entry.data[..max_len as usize])
+            .map_err(|e| e.with_context(DiskLayer::BlockCache, start, max_len as usize))?;
         self.stats.writebacks += 1;
+        self.inner_unflushed = true;
+        self.inner_unordered = true;
+        Ok(())
+    }
+
+    /// Write back the dirty cached blocks selected by `filter` (by `ordered` flag), marking them
+    /// clean.
+    fn write_back_dirty(&mut self, filter: impl Fn(&CacheEntry) -> bool) -> Result<()> {
+        // Snapshot keys so we can iterate while mutating entries.
+        let keys: Vec<u64> = self
+            .cache
+            .iter()
+            .filter(|(_, entry)| entry.dirty && filter(entry))
+            .map(|(k, _)| *k)
+            .collect();
+        for key in keys {
+            let start = key
+                .checked_mul(self.block_size as u64)
+                .ok_or(DiskError::OffsetOverflow)?;
+            let entry = self
+                .cache
+                .get_mut(&key)
+                .ok_or(DiskError::Io("cache missing key during write-back".into()))?;
+            if start < self.inner.capacity_bytes() {
+                let max_len =
+                    (self.inner.capacity_bytes() - start).min(self.block_size as u64) as usize;
+                self.inner
+                    .write_at(start, &entry.data[..max_len])
+                    .map_err(|e| e.with_context(DiskLayer::BlockCache, start, max_len))?;
+                self.stats.writebacks += 1;
+                self.inner_unflushed = true;
+                self.inner_unordered = true;
+            }
+            entry.dirty = false;
+            entry.ordered = false;
+        }
+        Ok(())
+    }
+
+    /// Propagate the pending write barrier to `inner`: write back the blocks dirtied before it,
+    /// then order them ahead of anything written afterwards.
+    fn propagate_barrier(&mut self) -> Result<()> {
+        if !self.barrier_pending {
+            return Ok(());
+        }
+        self.write_back_dirty(|entry| entry.ordered)?;
+        if self.inner_unordered {
+            self.inner
+                .write_barrier()
+                .map_err(|e| e.in_layer(DiskLayer::BlockCache))?;
+            self.inner_unordered = false;
+        }
+        self.barrier_pending = false;
+        Ok(())
+    }
+
+    fn has_unordered_dirty(&self) -> bool {
+        self.cache
+            .iter()
+            .any(|(_, entry)| entry.dirty && !entry.ordered)
+    }
+
+    /// Dirty `block_idx` with data written after the pending barrier.
+    fn prepare_block_write(&mut self, block_idx: u64) -> Result<()> {
+        if self
+            .cache
+            .peek(&block_idx)
+            .is_some_and(|entry| entry.ordered)
+        {
+            // The block holds data from before the barrier that must not be merged with newer
+            // data.
+            self.propagate_barrier()?;
+        }
         Ok(())
     }
 }
@@ -169,6 +302,8 @@ impl<D: VirtualDisk> VirtualDisk for BlockCachedDisk<D> {
             let remaining = buf.len() - pos;
             let chunk_len = (self.block_size - within).min(remaining);
 
+            self.prepare_block_write(block_idx)?;
+
             // Fast-path: full-block overwrite. If the block isn't cached, we can allocate
             // a fresh entry directly from the write buffer and skip the inner read.
             if within == 0 && chunk_len == self.block_size {
@@ -183,7 +318,11 @@ impl<D: VirtualDisk> VirtualDisk for BlockCachedDisk<D> {
                     data.try_reserve_exact(self.block_size)
                         .map_err(|_| DiskError::QuotaExceeded)?;
                     data.extend_from_slice(&buf[pos..pos + chunk_len]);
-                    let entry = CacheEntry { data, dirty: true };
+                    let entry = CacheEntry {
+                        data,
+                        dirty: true,
+                        ordered: false,
+                    };
                     self.insert_cache_entry(block_idx, entry)?;
                 }
             } else {
@@ -202,32 +341,47 @@ impl<D: VirtualDisk> VirtualDisk for BlockCachedDisk<D> {
     }
 
     fn flush(&mut self) -> Result<()> {
-        // Snapshot keys so we can iterate while mutating entries.
-        let keys: Vec<u64> = self.cache.iter().map(|(k, _)| *k).collect();
-        for key in keys {
-            let start = key
-                .checked_mul(self.block_size as u64)
-                .ok_or(DiskError::OffsetOverflow)?;
-            if start >= self.inner.capacity_bytes() {
-                continue;
-            }
-            let max_len =
-                (self.inner.capacity_bytes() - start).min(self.block_size as u64) as usize;
+        if self.has_unordered_dirty() {
+            self.propagate_barrier()?;
+        } else {
+            // Only pre-barrier data is dirty; the flush below orders it just as well.
+            self.barrier_pending = false;
+        }
+        self.write_back_dirty(|_| true)?;
+        self.batched_barriers = 0;
+        if !self.inner_unflushed {
+            return Ok(());
+        }
 
-            let entry = self
-                .cache
-                .get_mut(&key)
-                .ok_or(DiskError::Io("cache missing key during flush".into()))?;
-            if !entry.dirty {
-                continue;
-            }
+        self.inner
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::BlockCache))?;
+        self.stats.inner_flushes += 1;
+        self.inner_unflushed = false;
+        self.inner_unordered = false;
+        Ok(())
+    }
 
-            self.inner.write_at(start, &entry.data[..max_len])?;
-            self.stats.writebacks += 1;
-            entry.dirty = false;
+    fn write_barrier(&mut self) -> Result<()> {
+        self.batched_barriers += 1;
+        if self.batched_barriers > self.flush_batch_window {
+            return self.flush();
         }
 
-        self.inner.flush()
+        if !self.has_unordered_dirty() && (self.barrier_pending || !self.inner_unordered) {
+            // Nothing was written since the pending barrier or the last backend flush/barrier.
+            return Ok(());
+        }
+        // Only one barrier can be pending: order the previous group first. Without dirty blocks
+        // the new barrier just orders what already reached `inner`.
+        self.propagate_barrier()?;
+        for (_, entry) in self.cache.iter_mut() {
+            if entry.dirty {
+                entry.ordered = true;
+            }
+        }
+        self.barrier_pending = true;
+        Ok(())
     }
 
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
@@ -251,6 +405,9 @@ impl<D: VirtualDisk> VirtualDisk for BlockCachedDisk<D> {
             });
         }
 
+        // The discard reaches `inner` immediately, so it must be ordered after earlier writes.
+        self.propagate_barrier()?;
+
         let block_size_u64 = self.block_size as u64;
         let start_block = offset / block_size_u64;
         let end_block = (end - 1) / block_size_u64;
@@ -280,17 +437,32 @@ impl<D: VirtualDisk> VirtualDisk for BlockCachedDisk<D> {
                 continue;
             }
 
-            self.inner.write_at(start, &entry.data[..max_len])?;
+            self.inner
+                .write_at(start, &entry.data[..max_len])
+                .map_err(|e| e.with_context(DiskLayer::BlockCache, start, max_len))?;
             self.stats.writebacks += 1;
             entry.dirty = false;
         }
+        self.inner_unflushed = true;
+        self.inner_unordered = true;
 
         // Propagate to the underlying disk and invalidate overlapping cached blocks so subsequent
         // reads observe the post-discard state (e.g. unallocated sparse blocks reading as zero).
-        self.inner.discard_range(offset, len)?;
+        self.inner.discard_range(offset, len).map_err(|e| {
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            e.with_context(DiskLayer::BlockCache, offset, len)
+        })?;
         for key in keys {
             self.cache.pop(&key);
         }
         Ok(())
     }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        // Cached blocks never hold data past the old capacity (misses zero-fill the tail), so
+        // they stay valid once the inner disk grows.
+        self.inner
+            .resize(new_capacity)
+            .map_err(|e| e.in_layer(DiskLayer::BlockCache))
+    }
 }
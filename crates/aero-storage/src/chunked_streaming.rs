@@ -138,6 +138,9 @@ impl From<StreamingDiskError> for ChunkedStreamingDiskError {
                 ChunkedStreamingDiskError::OutOfBounds { offset, len, size }
             }
             StreamingDiskError::UrlNotAbsolute(s) => ChunkedStreamingDiskError::UrlNotAbsolute(s),
+            StreamingDiskError::Offline { chunk_index } => ChunkedStreamingDiskError::Http(
+                format!("disk is offline and chunk {chunk_index} is not cached"),
+            ),
         }
     }
 }
@@ -0,0 +1,292 @@
+//! Whole-image conversion between disk formats (e.g. exporting an OPFS-resident AeroSparse disk
+//! as QCOW2/VHD, or importing one).
+//!
+//! [`convert_image`] is the user-facing counterpart of [`crate::copy_disk`]: it reports progress,
+//! can be cancelled, optionally verifies the result, and validates the source geometry up front.
+
+use std::ops::ControlFlow;
+
+use thiserror::Error;
+
+use crate::formats::{COPY_BUF_BYTES, COPY_STATUS_WINDOW_BYTES};
+use crate::{
+    AllocatedRange, AllocationStatus, DiskError, DiskImage, StorageBackend, VirtualDisk,
+    VirtualDiskSend, SECTOR_SIZE,
+};
+
+/// Options for [`convert_image`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Logical sector size of the source medium. Every destination format uses
+    /// [`SECTOR_SIZE`]-byte sectors, so anything else (e.g. 2048 for an ISO image) is rejected.
+    pub source_sector_size: usize,
+    /// Re-read the source and destination after copying and compare them byte for byte.
+    pub verify: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            source_sector_size: SECTOR_SIZE,
+            verify: false,
+        }
+    }
+}
+
+/// Which pass a [`ConvertProgress`] update belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertPhase {
+    Copy,
+    Verify,
+}
+
+/// Progress reported to the [`convert_image`] callback after each chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertProgress {
+    pub phase: ConvertPhase,
+    /// Bytes of the source processed so far in this phase.
+    pub bytes_done: u64,
+    /// Source capacity in bytes.
+    pub bytes_total: u64,
+    /// Running copy-phase totals (see [`ConvertReport`]).
+    pub bytes_copied: u64,
+    pub bytes_skipped: u64,
+}
+
+/// Summary of a finished [`convert_image`] call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConvertReport {
+    /// Bytes read from the source and written to the destination.
+    pub bytes_copied: u64,
+    /// Bytes the source reported as zero/unallocated that were neither read nor written, because
+    /// the destination already reads as zeros there.
+    pub bytes_skipped: u64,
+    /// Bytes the source reported as zero/unallocated that had to be written as zeros, because the
+    /// destination holds data there (raw and fixed VHD images, or a parent image).
+    pub bytes_zeroed: u64,
+    /// Whether the verify pass ran (and passed).
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConvertError {
+    #[error(transparent)]
+    Disk(#[from] DiskError),
+
+    #[error(
+        "source sector size {source_sector_size} does not match destination sector size {destination_sector_size}"
+    )]
+    SectorSizeMismatch {
+        source_sector_size: usize,
+        destination_sector_size: usize,
+    },
+
+    #[error("source capacity {capacity} is not a multiple of the {sector_size}-byte sector size")]
+    UnalignedCapacity { capacity: u64, sector_size: usize },
+
+    #[error(
+        "destination capacity {destination_bytes} is smaller than source capacity {source_bytes}"
+    )]
+    DestinationTooSmall {
+        source_bytes: u64,
+        destination_bytes: u64,
+    },
+
+    /// The verify pass found the first differing byte at `offset`.
+    #[error("verification failed: destination differs from source at offset {offset}")]
+    VerifyMismatch { offset: u64 },
+
+    /// The progress callback returned [`ControlFlow::Break`]. The destination has been flushed
+    /// and holds a partial copy.
+    #[error("conversion cancelled")]
+    Cancelled,
+}
+
+/// Copy every byte of `src` into `dst`, then flush `dst`.
+///
+/// Ranges `src` reports as [`AllocationStatus::Zero`] or [`AllocationStatus::Unallocated`] are
+/// never read. They are not written either where `dst` already reads as zeros (a freshly created
+/// sparse image), so sparse regions stay sparse; elsewhere they are written as zeros. `dst` must
+/// be at least as large as `src`; any tail beyond the source capacity is left untouched.
+///
+/// `progress` is called after each chunk of up to 1 MiB (and after each skipped range); returning
+/// [`ControlFlow::Break`] stops the conversion with [`ConvertError::Cancelled`].
+pub fn convert_image<B>(
+    src: &mut dyn VirtualDisk,
+    dst: &mut DiskImage<B>,
+    opts: ConvertOptions,
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+) -> Result<ConvertReport, ConvertError>
+where
+    B: StorageBackend + VirtualDiskSend,
+{
+    if opts.source_sector_size != SECTOR_SIZE {
+        return Err(ConvertError::SectorSizeMismatch {
+            source_sector_size: opts.source_sector_size,
+            destination_sector_size: SECTOR_SIZE,
+        });
+    }
+    let capacity = src.capacity_bytes();
+    if !capacity.is_multiple_of(SECTOR_SIZE as u64) {
+        return Err(ConvertError::UnalignedCapacity {
+            capacity,
+            sector_size: SECTOR_SIZE,
+        });
+    }
+    if dst.capacity_bytes() < capacity {
+        return Err(ConvertError::DestinationTooSmall {
+            source_bytes: capacity,
+            destination_bytes: dst.capacity_bytes(),
+        });
+    }
+
+    let mut report = ConvertReport::default();
+    let result = copy_pass(src, dst, capacity, &mut report, progress);
+    // Flush even when cancelled so the destination is a consistent (partial) image.
+    dst.flush()?;
+    result?;
+
+    if opts.verify {
+        verify_pass(src, dst, capacity, &report, progress)?;
+        report.verified = true;
+    }
+    Ok(report)
+}
+
+fn copy_pass(
+    src: &mut dyn VirtualDisk,
+    dst: &mut dyn VirtualDisk,
+    capacity: u64,
+    report: &mut ConvertReport,
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+) -> Result<(), ConvertError> {
+    let mut buf = vec![0u8; COPY_BUF_BYTES];
+    let mut window = 0u64;
+    while window < capacity {
+        let window_len = (capacity - window).min(COPY_STATUS_WINDOW_BYTES);
+        for range in src.allocation_status(window, window_len)? {
+            if range.status == AllocationStatus::Data {
+                copy_range(src, dst, range, &mut buf, capacity, report, progress)?;
+            } else {
+                zero_range(dst, range, &mut buf, capacity, report, progress)?;
+            }
+        }
+        window += window_len;
+    }
+    Ok(())
+}
+
+fn copy_range(
+    src: &mut dyn VirtualDisk,
+    dst: &mut dyn VirtualDisk,
+    range: AllocatedRange,
+    buf: &mut [u8],
+    capacity: u64,
+    report: &mut ConvertReport,
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+) -> Result<(), ConvertError> {
+    let end = range.offset + range.len;
+    let mut off = range.offset;
+    while off < end {
+        let n = (end - off).min(buf.len() as u64) as usize;
+        src.read_at(off, &mut buf[..n])?;
+        dst.write_at(off, &buf[..n])?;
+        off += n as u64;
+        report.bytes_copied += n as u64;
+        report_copy(progress, off, capacity, report)?;
+    }
+    Ok(())
+}
+
+/// Make `range` of `dst` read as zeros, writing only where `dst` reports stored data.
+fn zero_range(
+    dst: &mut dyn VirtualDisk,
+    range: AllocatedRange,
+    buf: &mut [u8],
+    capacity: u64,
+    report: &mut ConvertReport,
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+) -> Result<(), ConvertError> {
+    buf.fill(0);
+    for dst_range in dst.allocation_status(range.offset, range.len)? {
+        let end = dst_range.offset + dst_range.len;
+        if dst_range.status != AllocationStatus::Data {
+            report.bytes_skipped += dst_range.len;
+            report_copy(progress, end, capacity, report)?;
+            continue;
+        }
+        let mut off = dst_range.offset;
+        while off < end {
+            let n = (end - off).min(buf.len() as u64) as usize;
+            dst.write_at(off, &buf[..n])?;
+            off += n as u64;
+            report.bytes_zeroed += n as u64;
+            report_copy(progress, off, capacity, report)?;
+        }
+    }
+    Ok(())
+}
+
+fn report_copy(
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+    bytes_done: u64,
+    bytes_total: u64,
+    report: &ConvertReport,
+) -> Result<(), ConvertError> {
+    report_progress(
+        progress,
+        ConvertPhase::Copy,
+        bytes_done,
+        bytes_total,
+        report,
+    )
+}
+
+fn report_progress(
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+    phase: ConvertPhase,
+    bytes_done: u64,
+    bytes_total: u64,
+    report: &ConvertReport,
+) -> Result<(), ConvertError> {
+    let update = ConvertProgress {
+        phase,
+        bytes_done,
+        bytes_total,
+        bytes_copied: report.bytes_copied,
+        bytes_skipped: report.bytes_skipped,
+    };
+    match progress(update) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(()) => Err(ConvertError::Cancelled),
+    }
+}
+
+fn verify_pass(
+    src: &mut dyn VirtualDisk,
+    dst: &mut dyn VirtualDisk,
+    capacity: u64,
+    report: &ConvertReport,
+    progress: &mut dyn FnMut(ConvertProgress) -> ControlFlow<()>,
+) -> Result<(), ConvertError> {
+    let mut src_buf = vec![0u8; COPY_BUF_BYTES];
+    let mut dst_buf = vec![0u8; COPY_BUF_BYTES];
+    let mut off = 0u64;
+    while off < capacity {
+        let n = (capacity - off).min(COPY_BUF_BYTES as u64) as usize;
+        src.read_at(off, &mut src_buf[..n])?;
+        dst.read_at(off, &mut dst_buf[..n])?;
+        if let Some(pos) = src_buf[..n]
+            .iter()
+            .zip(&dst_buf[..n])
+            .position(|(a, b)| a != b)
+        {
+            return Err(ConvertError::VerifyMismatch {
+                offset: off + pos as u64,
+            });
+        }
+        off += n as u64;
+        report_progress(progress, ConvertPhase::Verify, off, capacity, report)?;
+    }
+    Ok(())
+}
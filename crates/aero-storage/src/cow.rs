@@ -1,10 +1,17 @@
+use crate::disk::push_allocated_range;
 use crate::util::checked_range;
-use crate::{AeroSparseConfig, AeroSparseDisk, DiskError, Result, StorageBackend, VirtualDisk};
+use crate::{
+    AeroSparseConfig, AeroSparseDisk, AllocatedRange, AllocationStatus, DiskError, DiskLayer,
+    Result, StorageBackend, VirtualDisk,
+};
 
 /// Copy-on-write disk built from a read-only base disk plus a writable sparse overlay.
 ///
 /// Reads consult the overlay first; if the relevant overlay block is unallocated the data
 /// is read from the base. Writes always go to the overlay.
+///
+/// The overlay may be larger than the base after [`VirtualDisk::resize`]; the range past the end
+/// of the base is covered by the overlay alone and reads as zeros until written.
 pub struct AeroCowDisk<Base, OverlayBackend> {
     base: Base,
     overlay: AeroSparseDisk<OverlayBackend>,
@@ -23,6 +30,7 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             AeroSparseConfig {
                 disk_size_bytes: base.capacity_bytes(),
                 block_size_bytes,
+                ..Default::default()
             },
         )?;
         Ok(Self { base, overlay })
@@ -30,7 +38,7 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
 
     pub fn open(base: Base, overlay_backend: OverlayBackend) -> Result<Self> {
         let overlay = AeroSparseDisk::open(overlay_backend)?;
-        if overlay.capacity_bytes() != base.capacity_bytes() {
+        if overlay.capacity_bytes() < base.capacity_bytes() {
             return Err(DiskError::InvalidSparseHeader(
                 "overlay size does not match base disk size",
             ));
@@ -49,13 +57,44 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
     pub fn into_parts(self) -> (Base, AeroSparseDisk<OverlayBackend>) {
         (self.base, self.overlay)
     }
+
+    /// Read from the base disk, zero-filling anything past its end.
+    fn read_base(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let base_len = self.base.capacity_bytes();
+        let avail = base_len.saturating_sub(offset).min(buf.len() as u64) as usize;
+        if avail > 0 {
+            self.base
+                .read_at(offset, &mut buf[..avail])
+                .map_err(|e| e.with_context(DiskLayer::AeroCow, offset, avail))?;
+        }
+        buf[avail..].fill(0);
+        Ok(())
+    }
+
+    /// Write `data` at `within` bytes into overlay block `block_idx`, already allocated at `phys`.
+    fn write_overlay(
+        &mut self,
+        block_idx: u64,
+        phys: u64,
+        within: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        self.overlay
+            .write_to_alloc_table(block_idx, phys, within, data)
+            .map_err(|e| {
+                let offset = block_idx
+                    .saturating_mul(self.overlay.header().block_size_u64())
+                    .saturating_add(within as u64);
+                e.with_context(DiskLayer::AeroCow, offset, data.len())
+            })
+    }
 }
 
 impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk
     for AeroCowDisk<Base, OverlayBackend>
 {
     fn capacity_bytes(&self) -> u64 {
-        self.base.capacity_bytes()
+        self.overlay.capacity_bytes()
     }
 
     fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
@@ -71,9 +110,11 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             let chunk_len = (block_size as usize - within).min(remaining);
 
             if self.overlay.is_block_allocated(block_idx) {
-                self.overlay.read_at(abs, &mut buf[pos..pos + chunk_len])?;
+                self.overlay
+                    .read_at(abs, &mut buf[pos..pos + chunk_len])
+                    .map_err(|e| e.with_context(DiskLayer::AeroCow, abs, chunk_len))?;
             } else {
-                self.base.read_at(abs, &mut buf[pos..pos + chunk_len])?;
+                self.read_base(abs, &mut buf[pos..pos + chunk_len])?;
             }
 
             pos += chunk_len;
@@ -97,13 +138,15 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             let remaining = buf.len() - pos;
             let chunk_len = (block_size_usize - within).min(remaining);
 
-            let (phys, existed) = self.overlay.ensure_block_allocated(block_idx)?;
+            let (phys, existed) = self
+                .overlay
+                .ensure_block_allocated(block_idx)
+                .map_err(|e| e.with_context(DiskLayer::AeroCow, abs, chunk_len))?;
 
             let full_block_write = within == 0 && chunk_len as u64 == block_size;
             if full_block_write {
                 // No need to consult base; overwrite the whole block.
-                self.overlay
-                    .write_to_alloc_table(phys, 0, &buf[pos..pos + chunk_len])?;
+                self.write_overlay(block_idx, phys, 0, &buf[pos..pos + chunk_len])?;
                 pos += chunk_len;
                 continue;
             }
@@ -111,8 +154,7 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             if existed {
                 // Existing overlay blocks already contain the correct bytes for regions we are
                 // not touching; avoid a full-block read-modify-write.
-                self.overlay
-                    .write_to_alloc_table(phys, within, &buf[pos..pos + chunk_len])?;
+                self.write_overlay(block_idx, phys, within, &buf[pos..pos + chunk_len])?;
                 pos += chunk_len;
                 continue;
             }
@@ -136,9 +178,8 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             let mut remaining_prefix = within.min(max_len);
             while remaining_prefix > 0 {
                 let chunk = remaining_prefix.min(scratch.len());
-                self.base.read_at(base_off, &mut scratch[..chunk])?;
-                self.overlay
-                    .write_to_alloc_table(phys, overlay_off, &scratch[..chunk])?;
+                self.read_base(base_off, &mut scratch[..chunk])?;
+                self.write_overlay(block_idx, phys, overlay_off, &scratch[..chunk])?;
                 base_off = base_off
                     .checked_add(chunk as u64)
                     .ok_or(DiskError::OffsetOverflow)?;
@@ -147,8 +188,7 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
             }
 
             // The actual write.
-            self.overlay
-                .write_to_alloc_table(phys, within, &buf[pos..pos + chunk_len])?;
+            self.write_overlay(block_idx, phys, within, &buf[pos..pos + chunk_len])?;
 
             // Suffix after the write.
             let write_end = within + chunk_len;
@@ -160,9 +200,8 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
                 let mut remaining_suffix = max_len - write_end;
                 while remaining_suffix > 0 {
                     let chunk = remaining_suffix.min(scratch.len());
-                    self.base.read_at(base_off, &mut scratch[..chunk])?;
-                    self.overlay
-                        .write_to_alloc_table(phys, overlay_off, &scratch[..chunk])?;
+                    self.read_base(base_off, &mut scratch[..chunk])?;
+                    self.write_overlay(block_idx, phys, overlay_off, &scratch[..chunk])?;
                     base_off = base_off
                         .checked_add(chunk as u64)
                         .ok_or(DiskError::OffsetOverflow)?;
@@ -179,8 +218,12 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
 
     fn flush(&mut self) -> Result<()> {
         // Base is treated as read-only; flushing it is harmless.
-        self.base.flush()?;
-        self.overlay.flush()
+        self.base
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::AeroCow))?;
+        self.overlay
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::AeroCow))
     }
 
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
@@ -193,6 +236,62 @@ impl<Base: VirtualDisk, OverlayBackend: StorageBackend + crate::disk::VirtualDis
         // Discard is advisory and best-effort. Since the base disk is conceptually read-only, we
         // only discard from the overlay: this can reclaim sparse blocks and ensure subsequent reads
         // consult the base disk again.
-        self.overlay.discard_range(offset, len)
+        self.overlay.discard_range(offset, len).map_err(|e| {
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            e.with_context(DiskLayer::AeroCow, offset, len)
+        })
+    }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        self.overlay
+            .resize(new_capacity)
+            .map_err(|e| e.in_layer(DiskLayer::AeroCow))
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        checked_range(
+            offset,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            self.capacity_bytes(),
+        )?;
+
+        let block_size = self.overlay.header().block_size_u64();
+        let base_len = self.base.capacity_bytes();
+        let end = offset + len;
+        let mut out = Vec::new();
+        let mut cur = offset;
+        while cur < end {
+            // Gather a run of blocks that are all in the overlay or all passed through to the base,
+            // so the base is queried once per run rather than once per block.
+            let in_overlay = self.overlay.is_block_allocated(cur / block_size);
+            let mut run_end = (cur / block_size + 1).saturating_mul(block_size).min(end);
+            while run_end < end
+                && self.overlay.is_block_allocated(run_end / block_size) == in_overlay
+            {
+                run_end = (run_end / block_size + 1)
+                    .saturating_mul(block_size)
+                    .min(end);
+            }
+
+            if in_overlay {
+                push_allocated_range(&mut out, cur, run_end - cur, AllocationStatus::Data);
+            } else {
+                // Past the end of the base the overlay alone covers the disk.
+                let base_end = run_end.min(base_len).max(cur);
+                if base_end > cur {
+                    for r in self.base.allocation_status(cur, base_end - cur)? {
+                        push_allocated_range(&mut out, r.offset, r.len, r.status);
+                    }
+                }
+                push_allocated_range(
+                    &mut out,
+                    base_end,
+                    run_end - base_end,
+                    AllocationStatus::Unallocated,
+                );
+            }
+            cur = run_end;
+        }
+        Ok(out)
     }
 }
@@ -1,8 +1,51 @@
-use crate::util::checked_range;
-use crate::{DiskError, Result, StorageBackend};
+use crate::util::{check_grow, checked_range};
+use crate::{DiskError, DiskLayer, Result, StorageBackend};
 
 pub const SECTOR_SIZE: usize = 512;
 
+/// Allocation state of a byte range reported by [`VirtualDisk::allocation_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationStatus {
+    /// Backed by stored data (which may still happen to be all zeros).
+    Data,
+    /// Explicitly recorded as zeros by the image format (e.g. a QCOW2 zero cluster).
+    Zero,
+    /// Not stored anywhere in the image chain; reads as zeros.
+    Unallocated,
+}
+
+/// A contiguous byte range with a single [`AllocationStatus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatedRange {
+    pub offset: u64,
+    pub len: u64,
+    pub status: AllocationStatus,
+}
+
+/// Append `[offset, offset + len)` to `out`, merging it into the last range when adjacent with the
+/// same status.
+pub(crate) fn push_allocated_range(
+    out: &mut Vec<AllocatedRange>,
+    offset: u64,
+    len: u64,
+    status: AllocationStatus,
+) {
+    if len == 0 {
+        return;
+    }
+    if let Some(last) = out.last_mut() {
+        if last.status == status && last.offset + last.len == offset {
+            last.len += len;
+            return;
+        }
+    }
+    out.push(AllocatedRange {
+        offset,
+        len,
+        status,
+    });
+}
+
 /// Helper trait: conditionally requires `Send` depending on the target.
 ///
 /// On native targets, disk backends are frequently moved across threads (worker pools, async
@@ -35,8 +78,22 @@ pub trait VirtualDisk: VirtualDiskSend {
 
     fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()>;
     fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()>;
+
+    /// Full durability barrier: every write that completed before the call is durable once it
+    /// returns.
     fn flush(&mut self) -> Result<()>;
 
+    /// Ordering-only barrier: every write issued before the call becomes durable no later than
+    /// any write issued after it. Nothing is guaranteed to be durable when it returns.
+    ///
+    /// Storage controllers use this to keep guest write ordering across a guest FLUSH whose
+    /// backend flush is batched with later ones (see [`crate::BlockCachedDisk`]). The default
+    /// implementation performs a full [`VirtualDisk::flush`], which is always a valid barrier;
+    /// disks whose writes reach durable storage in issue order can make it a no-op.
+    fn write_barrier(&mut self) -> Result<()> {
+        self.flush()
+    }
+
     /// Best-effort deallocation (discard/TRIM) of the given byte range.
     ///
     /// The default implementation validates that the range is in-bounds and then performs no
@@ -68,6 +125,35 @@ pub trait VirtualDisk: VirtualDiskSend {
         Ok(())
     }
 
+    /// Report which parts of `[offset, offset + len)` hold data.
+    ///
+    /// The returned ranges are sorted, non-overlapping and cover the requested range exactly;
+    /// adjacent ranges with the same status are merged. Ranges inherited from a parent/base disk
+    /// report the parent's status. [`AllocationStatus::Zero`] and
+    /// [`AllocationStatus::Unallocated`] ranges both read as zeros, so copying tools can skip them.
+    ///
+    /// The default implementation reports the whole range as [`AllocationStatus::Data`]; sparse
+    /// formats override it using their allocation metadata.
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        checked_range(
+            offset,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            self.capacity_bytes(),
+        )?;
+        let mut out = Vec::new();
+        push_allocated_range(&mut out, offset, len, AllocationStatus::Data);
+        Ok(out)
+    }
+
+    /// Grow the disk to `new_capacity` bytes.
+    ///
+    /// Only growing is supported: shrinking returns [`DiskError::Unsupported`], and the newly
+    /// exposed range reads back as zeros. The default implementation reports the operation as
+    /// unsupported; formats that can extend their metadata in place override it.
+    fn resize(&mut self, _new_capacity: u64) -> Result<()> {
+        Err(DiskError::Unsupported("resize"))
+    }
+
     fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<()> {
         if !buf.len().is_multiple_of(SECTOR_SIZE) {
             return Err(DiskError::UnalignedLength {
@@ -176,7 +262,10 @@ impl<D: VirtualDisk> VirtualDisk for ReadOnlyDisk<D> {
     }
 
     fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
-        self.inner.read_at(offset, buf)
+        let len = buf.len();
+        self.inner
+            .read_at(offset, buf)
+            .map_err(|e| e.with_context(DiskLayer::ReadOnly, offset, len))
     }
 
     fn write_at(&mut self, _offset: u64, _buf: &[u8]) -> Result<()> {
@@ -189,6 +278,14 @@ impl<D: VirtualDisk> VirtualDisk for ReadOnlyDisk<D> {
         // use in practice.
         Ok(())
     }
+
+    fn write_barrier(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        self.inner.allocation_status(offset, len)
+    }
 }
 
 impl<T: VirtualDisk + ?Sized> VirtualDisk for Box<T> {
@@ -208,10 +305,22 @@ impl<T: VirtualDisk + ?Sized> VirtualDisk for Box<T> {
         (**self).flush()
     }
 
+    fn write_barrier(&mut self) -> Result<()> {
+        (**self).write_barrier()
+    }
+
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
         (**self).discard_range(offset, len)
     }
 
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        (**self).resize(new_capacity)
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        (**self).allocation_status(offset, len)
+    }
+
     fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<()> {
         (**self).read_sectors(lba, buf)
     }
@@ -238,10 +347,22 @@ impl<T: VirtualDisk + ?Sized> VirtualDisk for &mut T {
         (**self).flush()
     }
 
+    fn write_barrier(&mut self) -> Result<()> {
+        (**self).write_barrier()
+    }
+
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
         (**self).discard_range(offset, len)
     }
 
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        (**self).resize(new_capacity)
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        (**self).allocation_status(offset, len)
+    }
+
     fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<()> {
         (**self).read_sectors(lba, buf)
     }
@@ -283,15 +404,48 @@ impl<B: StorageBackend + VirtualDiskSend> VirtualDisk for RawDisk<B> {
 
     fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
         checked_range(offset, buf.len(), self.capacity)?;
-        self.backend.read_at(offset, buf)
+        let len = buf.len();
+        self.backend
+            .read_at(offset, buf)
+            .map_err(|e| e.with_context(DiskLayer::Raw, offset, len))
     }
 
     fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
         checked_range(offset, buf.len(), self.capacity)?;
-        self.backend.write_at(offset, buf)
+        self.backend
+            .write_at(offset, buf)
+            .map_err(|e| e.with_context(DiskLayer::Raw, offset, buf.len()))
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.backend.flush()
+        self.backend.flush().map_err(|e| e.in_layer(DiskLayer::Raw))
+    }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        if !check_grow(self.capacity, new_capacity)? {
+            return Ok(());
+        }
+        // Backends zero-extend on `set_len`, but a backend may already be longer than the disk
+        // (e.g. a shared buffer); clear any stale bytes that are about to become visible.
+        let backend_len = self.backend.len().map_err(|e| e.in_layer(DiskLayer::Raw))?;
+        if backend_len > self.capacity {
+            let stale_end = backend_len.min(new_capacity);
+            let zeros = [0u8; 4096];
+            let mut off = self.capacity;
+            while off < stale_end {
+                let len = (stale_end - off).min(zeros.len() as u64) as usize;
+                self.backend
+                    .write_at(off, &zeros[..len])
+                    .map_err(|e| e.with_context(DiskLayer::Raw, off, len))?;
+                off += len as u64;
+            }
+        }
+        if backend_len < new_capacity {
+            self.backend
+                .set_len(new_capacity)
+                .map_err(|e| e.in_layer(DiskLayer::Raw))?;
+        }
+        self.capacity = new_capacity;
+        Ok(())
     }
 }
@@ -1,7 +1,158 @@
+use std::fmt;
+
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, DiskError>;
 
+/// Disk wrapper or format that attached a [`DiskErrorContext`] to an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiskLayer {
+    /// [`crate::RawDisk`].
+    Raw,
+    /// [`crate::Qcow2Disk`].
+    Qcow2,
+    /// [`crate::VhdDisk`].
+    Vhd,
+    /// [`crate::AeroSparseDisk`].
+    AeroSparse,
+    /// [`crate::AeroCowDisk`].
+    AeroCow,
+    /// [`crate::BlockCachedDisk`].
+    BlockCache,
+    /// [`crate::ReadOnlyDisk`].
+    ReadOnly,
+    /// The HTTP Range streaming disk (native only).
+    Streaming,
+}
+
+impl DiskLayer {
+    /// Short name used when rendering an error chain.
+    pub fn name(self) -> &'static str {
+        match self {
+            DiskLayer::Raw => "raw",
+            DiskLayer::Qcow2 => "qcow2",
+            DiskLayer::Vhd => "vhd",
+            DiskLayer::AeroSparse => "aerosparse",
+            DiskLayer::AeroCow => "cow",
+            DiskLayer::BlockCache => "block-cache",
+            DiskLayer::ReadOnly => "read-only",
+            DiskLayer::Streaming => "streaming",
+        }
+    }
+}
+
+impl fmt::Display for DiskLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where in the disk stack an error passed through.
+///
+/// `offset`/`len` describe the operation the layer was performing when its inner disk or backend
+/// failed, in that layer's own address space (e.g. a QCOW2 layer reports the offset in the image
+/// file, not the guest offset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskErrorContext {
+    pub layer: DiskLayer,
+    pub offset: Option<u64>,
+    pub len: Option<usize>,
+    /// Chunk index, for layers that fetch data in chunks.
+    pub chunk: Option<u64>,
+}
+
+impl DiskErrorContext {
+    pub fn new(layer: DiskLayer) -> Self {
+        Self {
+            layer,
+            offset: None,
+            len: None,
+            chunk: None,
+        }
+    }
+
+    pub fn at(layer: DiskLayer, offset: u64, len: usize) -> Self {
+        Self {
+            offset: Some(offset),
+            len: Some(len),
+            ..Self::new(layer)
+        }
+    }
+}
+
+impl fmt::Display for DiskErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.layer)?;
+        let mut sep = '[';
+        for (key, value) in [
+            ("offset", self.offset),
+            ("len", self.len.map(|len| len as u64)),
+            ("chunk", self.chunk),
+        ] {
+            if let Some(value) = value {
+                write!(f, "{sep}{key}={value}")?;
+                sep = ' ';
+            }
+        }
+        if sep == ' ' {
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the rest of a context chain: `" > next[..]"` for another layer, or `": cause"` once the
+/// root error is reached.
+struct ChainTail<'a>(&'a DiskError);
+
+impl fmt::Display for ChainTail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            DiskError::Context { context, source } => {
+                write!(f, " > {context}{}", ChainTail(source))
+            }
+            root => write!(f, ": {root}"),
+        }
+    }
+}
+
+/// Native `std::io::Error` kept as the source of [`DiskError::NativeIo`].
+///
+/// The error is shared so [`DiskError`] stays `Clone`; two sources compare equal when their kind
+/// and message match.
+#[derive(Debug, Clone)]
+pub struct IoSource(std::sync::Arc<std::io::Error>);
+
+impl IoSource {
+    pub fn get(&self) -> &std::io::Error {
+        &self.0
+    }
+
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.0.kind()
+    }
+}
+
+impl PartialEq for IoSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for IoSource {}
+
+impl fmt::Display for IoSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for IoSource {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&*self.0)
+    }
+}
+
 /// Unified error type for Aero disk/storage operations.
 ///
 /// This enum is used by both native helpers (e.g. host-side streaming) and
@@ -11,8 +162,17 @@ pub type Result<T> = std::result::Result<T, DiskError>;
 ///
 /// Note: [`DiskError::Io`] intentionally stores a human-readable `String` rather
 /// than `std::io::Error` so wasm32 implementations can surface errors originating
-/// from JavaScript/DOM APIs without requiring a platform-specific error type.
-#[derive(Debug, Error)]
+/// from JavaScript/DOM APIs without requiring a platform-specific error type. Native
+/// backends report [`DiskError::NativeIo`] instead, which keeps the `std::io::Error`
+/// as its [`std::error::Error::source`].
+///
+/// Wrappers and formats attach a [`DiskError::Context`] (see [`DiskError::with_context`])
+/// to errors coming out of their inner disk or backend, so a failure deep in a stack
+/// displays as e.g. `block-cache[offset=0 len=4096] > qcow2[offset=196608 len=4096] >
+/// raw[offset=196608 len=4096]: io error: ...`. Errors a layer produces itself (bounds
+/// checks, corrupt metadata) are returned bare. Use [`DiskError::root`] to match on the
+/// underlying cause.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum DiskError {
     #[error("unaligned buffer length {len} (expected multiple of {alignment})")]
     UnalignedLength { len: usize, alignment: usize },
@@ -42,6 +202,16 @@ pub enum DiskError {
     #[error("corrupt sparse image: {0}")]
     CorruptSparseImage(&'static str),
 
+    /// An AeroSparse block's data does not match its recorded CRC32C.
+    #[error(
+        "checksum mismatch in sparse block {block_idx}: stored {expected:#010x}, computed {actual:#010x}"
+    )]
+    ChecksumMismatch {
+        block_idx: u64,
+        expected: u32,
+        actual: u32,
+    },
+
     #[error("backend not supported: {0}")]
     NotSupported(String),
 
@@ -62,4 +232,95 @@ pub enum DiskError {
     /// This is a catch-all for errors that do not map to a more structured variant.
     #[error("io error: {0}")]
     Io(String),
+
+    /// I/O failure from a native `std::io::Error`, kept as the error source. `message` says what
+    /// the backend was doing (operation, path, offset).
+    ///
+    /// Only native backends produce this (see [`DiskError::native_io`], which is not available on
+    /// wasm32); browser backends report [`DiskError::Io`].
+    #[error("io error: {message}: {source}")]
+    NativeIo {
+        message: String,
+        #[source]
+        source: IoSource,
+    },
+
+    /// `source` propagated out of a wrapper or format; see [`DiskError::with_context`].
+    #[error("{context}{}", ChainTail(source))]
+    Context {
+        context: DiskErrorContext,
+        #[source]
+        source: Box<DiskError>,
+    },
+}
+
+impl DiskError {
+    /// Record that this error propagated through `layer` while it was accessing `len` bytes at
+    /// `offset` of its inner disk or backend.
+    pub fn with_context(self, layer: DiskLayer, offset: u64, len: usize) -> Self {
+        self.context(DiskErrorContext::at(layer, offset, len))
+    }
+
+    /// Like [`DiskError::with_context`], for operations without a byte range (flush, resize).
+    pub fn in_layer(self, layer: DiskLayer) -> Self {
+        self.context(DiskErrorContext::new(layer))
+    }
+
+    /// Wrap this error in `context`.
+    pub fn context(self, context: DiskErrorContext) -> Self {
+        DiskError::Context {
+            context,
+            source: Box::new(self),
+        }
+    }
+
+    /// The underlying error with every [`DiskError::Context`] layer stripped.
+    pub fn root(&self) -> &DiskError {
+        let mut err = self;
+        while let DiskError::Context { source, .. } = err {
+            err = &**source;
+        }
+        err
+    }
+
+    /// Consuming form of [`DiskError::root`].
+    pub fn into_root(self) -> DiskError {
+        let mut err = self;
+        while let DiskError::Context { source, .. } = err {
+            err = *source;
+        }
+        err
+    }
+
+    /// Contexts attached to this error, outermost first.
+    pub fn contexts(&self) -> impl Iterator<Item = &DiskErrorContext> {
+        let mut next = Some(self);
+        std::iter::from_fn(move || match next? {
+            DiskError::Context { context, source } => {
+                next = Some(&**source);
+                Some(context)
+            }
+            _ => {
+                next = None;
+                None
+            }
+        })
+    }
+
+    /// A [`DiskError::NativeIo`] for `err`, described by `message`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn native_io(message: impl Into<String>, err: std::io::Error) -> Self {
+        DiskError::NativeIo {
+            message: message.into(),
+            source: IoSource(std::sync::Arc::new(err)),
+        }
+    }
+
+    /// The native I/O error at the root of the chain, if any.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self.root() {
+            DiskError::NativeIo { source, .. } => Some(source.get()),
+            _ => None,
+        }
+    }
 }
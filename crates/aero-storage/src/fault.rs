@@ -0,0 +1,195 @@
+//! Deterministic latency/fault injection for storage controller robustness testing.
+//!
+//! Storage controllers consult a [`StorageFaultInjector`] right before they issue a request to
+//! their [`crate::VirtualDisk`]. The injector decides whether the request fails (without touching
+//! the disk) and how much guest time should pass before the controller reports completion.
+//! Controllers that honour the delay complete the request on a later processing step, so guests
+//! observe slow storage (e.g. a throttled OPFS backend) as real outstanding I/O.
+
+use std::ops::Range;
+
+use crate::DiskError;
+
+/// Kind of disk request a controller is about to issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageRequestKind {
+    Read,
+    Write,
+    Flush,
+}
+
+/// A disk request as seen by a storage controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageRequest {
+    pub kind: StorageRequestKind,
+    /// First 512-byte sector addressed by the request (0 for flushes).
+    pub lba: u64,
+    /// Number of sectors addressed by the request (0 for flushes).
+    pub sectors: u64,
+}
+
+impl StorageRequest {
+    fn overlaps(&self, range: &Range<u64>) -> bool {
+        let end = self.lba.saturating_add(self.sectors);
+        self.lba < range.end && range.start < end
+    }
+}
+
+/// Guest-time delay added before each request completes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageFaultDelay {
+    #[default]
+    None,
+    Fixed {
+        ns: u64,
+    },
+    /// Uniformly distributed in `min_ns..=max_ns`, drawn from a generator seeded with
+    /// [`StorageFaultPolicy::seed`].
+    Uniform {
+        min_ns: u64,
+        max_ns: u64,
+    },
+}
+
+/// Error reported for an injected failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageFaultError {
+    /// A generic I/O failure ([`DiskError::Io`]).
+    Io,
+    /// The backend is temporarily unavailable ([`DiskError::BackendUnavailable`]); controllers
+    /// report this as a retryable error.
+    BackendUnavailable,
+    /// The backing store ran out of quota ([`DiskError::QuotaExceeded`]).
+    QuotaExceeded,
+}
+
+impl StorageFaultError {
+    pub fn to_disk_error(self) -> DiskError {
+        match self {
+            StorageFaultError::Io => DiskError::Io("injected storage fault".to_string()),
+            StorageFaultError::BackendUnavailable => DiskError::BackendUnavailable,
+            StorageFaultError::QuotaExceeded => DiskError::QuotaExceeded,
+        }
+    }
+}
+
+/// What to inject into the requests issued by a storage controller.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageFaultPolicy {
+    pub delay: StorageFaultDelay,
+    /// Seed for randomized delays. The same seed and request sequence always produce the same
+    /// delays.
+    pub seed: u64,
+    /// Fail every Nth request (counting from 1) with the given error. `N == 0` never fails.
+    pub fail_every_nth: Option<(u64, StorageFaultError)>,
+    /// Fail every request that touches the given sector range with the given error.
+    pub fail_lba_range: Option<(Range<u64>, StorageFaultError)>,
+}
+
+/// Injected delay/failure counts, accumulated since the injector was installed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageFaultCounters {
+    /// Requests seen by the injector.
+    pub requests: u64,
+    /// Requests whose completion was delayed.
+    pub delayed: u64,
+    /// Sum of all injected delays, in nanoseconds of guest time.
+    pub delay_ns_total: u64,
+    /// Requests failed by [`StorageFaultPolicy::fail_every_nth`].
+    pub failed_nth: u64,
+    /// Requests failed by [`StorageFaultPolicy::fail_lba_range`].
+    pub failed_lba_range: u64,
+}
+
+impl StorageFaultCounters {
+    /// Add `other` into `self` (e.g. to total the counters of several controllers).
+    pub fn accumulate(&mut self, other: &StorageFaultCounters) {
+        self.requests += other.requests;
+        self.delayed += other.delayed;
+        self.delay_ns_total += other.delay_ns_total;
+        self.failed_nth += other.failed_nth;
+        self.failed_lba_range += other.failed_lba_range;
+    }
+}
+
+/// The injector's verdict for one request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageFaultOutcome {
+    /// Guest time to wait before completing the request (0 completes it immediately).
+    pub delay_ns: u64,
+    /// If set, fail the request with this error instead of issuing it to the disk.
+    pub error: Option<StorageFaultError>,
+}
+
+/// Applies a [`StorageFaultPolicy`] to the requests of one storage controller.
+#[derive(Debug, Clone)]
+pub struct StorageFaultInjector {
+    policy: StorageFaultPolicy,
+    rng_state: u64,
+    counters: StorageFaultCounters,
+}
+
+impl StorageFaultInjector {
+    pub fn new(policy: StorageFaultPolicy) -> Self {
+        Self {
+            rng_state: policy.seed,
+            policy,
+            counters: StorageFaultCounters::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &StorageFaultPolicy {
+        &self.policy
+    }
+
+    pub fn counters(&self) -> StorageFaultCounters {
+        self.counters
+    }
+
+    /// Decide the fate of `req`, which the controller is about to issue.
+    pub fn on_request(&mut self, req: StorageRequest) -> StorageFaultOutcome {
+        self.counters.requests += 1;
+
+        let mut error = None;
+        if let Some((n, err)) = self.policy.fail_every_nth {
+            if n != 0 && self.counters.requests.is_multiple_of(n) {
+                self.counters.failed_nth += 1;
+                error = Some(err);
+            }
+        }
+        if error.is_none() {
+            if let Some((range, err)) = &self.policy.fail_lba_range {
+                if req.overlaps(range) {
+                    self.counters.failed_lba_range += 1;
+                    error = Some(*err);
+                }
+            }
+        }
+
+        let delay_ns = match self.policy.delay {
+            StorageFaultDelay::None => 0,
+            StorageFaultDelay::Fixed { ns } => ns,
+            StorageFaultDelay::Uniform { min_ns, max_ns } => {
+                let (lo, hi) = (min_ns.min(max_ns), min_ns.max(max_ns));
+                match (hi - lo).checked_add(1) {
+                    Some(span) => lo + splitmix64(&mut self.rng_state) % span,
+                    None => splitmix64(&mut self.rng_state),
+                }
+            }
+        };
+        if delay_ns != 0 {
+            self.counters.delayed += 1;
+            self.counters.delay_ns_total = self.counters.delay_ns_total.saturating_add(delay_ns);
+        }
+
+        StorageFaultOutcome { delay_ns, error }
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -1,10 +1,18 @@
-use crate::{AeroSparseDisk, DiskError, Qcow2Disk, RawDisk, Result, StorageBackend, VhdDisk};
+use crate::{
+    AeroCowDisk, AeroSparseDisk, AllocatedRange, AllocationStatus, DiskError, Qcow2Disk, RawDisk,
+    Result, StorageBackend, VhdDisk, VirtualDisk,
+};
 
 const QCOW2_MAGIC: [u8; 4] = *b"QFI\xfb";
 const AEROSPAR_MAGIC: [u8; 8] = *b"AEROSPAR";
 const VHD_COOKIE: [u8; 8] = *b"conectix";
 const VHD_FOOTER_SIZE: usize = crate::SECTOR_SIZE;
 
+// `copy_disk` and `convert::convert_image` query allocation in windows of this size to bound the
+// range list, and copy through a buffer of `COPY_BUF_BYTES`.
+pub(crate) const COPY_STATUS_WINDOW_BYTES: u64 = 256 * 1024 * 1024;
+pub(crate) const COPY_BUF_BYTES: usize = 1024 * 1024;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum DiskFormat {
     Raw,
@@ -13,139 +21,151 @@ pub enum DiskFormat {
     Vhd,
 }
 
+/// How strongly an image matched a [`DiskFormat`] during detection.
+///
+/// Variants are ordered from weakest to strongest.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum DetectionConfidence {
+    /// No signature matched; any image can be opened as raw bytes.
+    Fallback,
+    /// The format's magic matched but the image is too small to hold a complete header. Opening
+    /// it as that format reports a corruption error rather than silently treating it as raw.
+    Truncated,
+    /// The format's magic matched and the header fields checked during detection are plausible.
+    Signature,
+}
+
+/// One possible interpretation of an image, as reported by [`detect_format_candidates`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FormatCandidate {
+    pub format: DiskFormat,
+    pub confidence: DetectionConfidence,
+}
+
 /// Detect the on-disk image format from magic values.
 ///
-/// Detection is conservative: unknown images fall back to [`DiskFormat::Raw`].
+/// Detection is conservative: unknown images fall back to [`DiskFormat::Raw`]. This returns the
+/// first entry of [`detect_format_candidates`].
 pub fn detect_format<B: StorageBackend>(backend: &mut B) -> Result<DiskFormat> {
+    let candidates = detect_format_candidates(backend)?;
+    Ok(candidates[0].format)
+}
+
+/// List every format the image could plausibly be, in the order [`detect_format`] prefers them.
+///
+/// The list is never empty and always ends with [`DiskFormat::Raw`] at
+/// [`DetectionConfidence::Fallback`]. More than two entries, or a preferred entry that is not
+/// [`DetectionConfidence::Signature`], means detection was ambiguous (for example a raw image
+/// whose first sector happens to look like a VHD footer); callers may want to warn and let the
+/// user pick a format via [`DiskImage::open_with_format`].
+pub fn detect_format_candidates<B: StorageBackend>(
+    backend: &mut B,
+) -> Result<Vec<FormatCandidate>> {
     let len = backend.len()?;
+    let mut candidates = Vec::new();
+
+    // Read up to the first 12 bytes so we can check both the QCOW2 version field (big-endian at
+    // offset 4) and the AeroSparse version field (little-endian at offset 8) without issuing
+    // multiple small reads.
+    let mut head = [0u8; 12];
+    let head_len = len.min(head.len() as u64) as usize;
+    if head_len >= 4 {
+        backend.read_at(0, &mut head[..head_len])?;
+    }
 
     // QCOW2: check the magic and a plausible version field. A QCOW2 header is at least 72 bytes.
     //
     // For truncated images (< 72 bytes) that still match the magic, treat them as QCOW2 so callers
     // get a corruption error instead of silently falling back to raw. For non-truncated images,
     // keep detection conservative by only accepting v2/v3.
-    if (4..8).contains(&len) {
-        let mut first4 = [0u8; 4];
-        backend.read_at(0, &mut first4)?;
-        if first4 == QCOW2_MAGIC {
-            return Ok(DiskFormat::Qcow2);
+    if head_len >= 4 && head[..4] == QCOW2_MAGIC {
+        if len < 72 {
+            candidates.push(candidate(DiskFormat::Qcow2, DetectionConfidence::Truncated));
+        } else {
+            let version = be_u32(&head[4..8]);
+            if version == 2 || version == 3 {
+                candidates.push(candidate(DiskFormat::Qcow2, DetectionConfidence::Signature));
+            }
         }
     }
 
-    if len >= 8 {
-        // Read the first 12 bytes when possible so we can check both the QCOW2 version field
-        // (big-endian at offset 4) and the AeroSparse version field (little-endian at offset 8)
-        // without issuing multiple small reads.
-        if len >= 12 {
-            let mut first12 = [0u8; 12];
-            backend.read_at(0, &mut first12)?;
-            let mut first8 = [0u8; 8];
-            first8.copy_from_slice(&first12[..8]);
-
-            if first8[..4] == QCOW2_MAGIC {
-                // Any QCOW2 magic in a file too small to contain the full v2 header should be
-                // treated as QCOW2 so callers see "header truncated" instead of silently opening
-                // as raw.
-                if len < 72 {
-                    return Ok(DiskFormat::Qcow2);
-                }
-                let version = be_u32(&first8[4..8]);
-                if version == 2 || version == 3 {
-                    return Ok(DiskFormat::Qcow2);
-                }
-            }
-
-            // AeroSparse: check magic plus a minimally plausible version field.
-            //
-            // We intentionally avoid fully validating the header here; `open_auto` should attempt to
-            // open the image and report a corruption/unsupported error instead of silently treating an
-            // AeroSparse-looking image as raw.
-            if first8 == AEROSPAR_MAGIC {
-                // If the file is too small to contain a complete header, still treat it as AeroSparse
-                // so callers get a corruption error instead of silently falling back to raw.
-                if len < 64 {
-                    return Ok(DiskFormat::AeroSparse);
-                }
-
-                let version =
-                    u32::from_le_bytes([first12[8], first12[9], first12[10], first12[11]]);
-                if version == 1 {
-                    return Ok(DiskFormat::AeroSparse);
-                }
+    // AeroSparse: check magic plus a minimally plausible version field.
+    //
+    // We intentionally avoid fully validating the header here; `open_auto` should attempt to
+    // open the image and report a corruption/unsupported error instead of silently treating an
+    // AeroSparse-looking image as raw.
+    if head_len >= 8 && head[..8] == AEROSPAR_MAGIC {
+        // If the file is too small to contain a complete header, still treat it as AeroSparse
+        // so callers get a corruption error instead of silently falling back to raw.
+        if len < 64 {
+            candidates.push(candidate(
+                DiskFormat::AeroSparse,
+                DetectionConfidence::Truncated,
+            ));
+        } else {
+            let version = u32::from_le_bytes([head[8], head[9], head[10], head[11]]);
+            if version == 1 || version == 2 {
+                candidates.push(candidate(
+                    DiskFormat::AeroSparse,
+                    DetectionConfidence::Signature,
+                ));
             }
+        }
+    }
 
-            // VHD dynamic disks commonly store a footer copy at offset 0.
-            if first8 == VHD_COOKIE {
-                // If the file begins with the VHD cookie but is too small to contain a complete footer,
-                // still treat it as a VHD so callers get a structured corruption error instead of
-                // silently falling back to raw.
-                if len < VHD_FOOTER_SIZE as u64 {
-                    return Ok(DiskFormat::Vhd);
-                }
-
-                let mut footer = [0u8; VHD_FOOTER_SIZE];
-                backend.read_at(0, &mut footer)?;
-                if looks_like_vhd_footer(&footer, len) {
-                    // For fixed disks, a valid footer at offset 0 implies the optional footer copy is
-                    // present, meaning the file must be large enough to contain:
-                    //   footer_copy (512) + data (current_size) + eof_footer (512)
-                    //
-                    // Without this check, a raw disk image whose first sector coincidentally resembles
-                    // a VHD footer could be misclassified as a VHD and then fail to open.
-                    let disk_type = be_u32(&footer[60..64]);
-                    if disk_type == 2 {
-                        let current_size = be_u64(&footer[48..56]);
-                        if let Some(required) =
-                            current_size.checked_add((VHD_FOOTER_SIZE as u64) * 2)
-                        {
-                            if len >= required {
-                                return Ok(DiskFormat::Vhd);
-                            }
-                        }
-                    } else {
-                        return Ok(DiskFormat::Vhd);
-                    }
-                }
-            }
+    // VHD dynamic disks commonly store a footer copy at offset 0.
+    if head_len >= 8 && head[..8] == VHD_COOKIE {
+        // If the file begins with the VHD cookie but is too small to contain a complete footer,
+        // still treat it as a VHD so callers get a structured corruption error instead of
+        // silently falling back to raw.
+        if len < VHD_FOOTER_SIZE as u64 {
+            candidates.push(candidate(DiskFormat::Vhd, DetectionConfidence::Truncated));
         } else {
-            let mut first8 = [0u8; 8];
-            backend.read_at(0, &mut first8)?;
-            if first8[..4] == QCOW2_MAGIC {
-                if len < 72 {
-                    return Ok(DiskFormat::Qcow2);
-                }
-                let version = be_u32(&first8[4..8]);
-                if version == 2 || version == 3 {
-                    return Ok(DiskFormat::Qcow2);
+            let mut footer = [0u8; VHD_FOOTER_SIZE];
+            backend.read_at(0, &mut footer)?;
+            if looks_like_vhd_footer(&footer, len) {
+                // For fixed disks, a valid footer at offset 0 implies the optional footer copy is
+                // present, meaning the file must be large enough to contain:
+                //   footer_copy (512) + data (current_size) + eof_footer (512)
+                //
+                // Without this check, a raw disk image whose first sector coincidentally resembles
+                // a VHD footer could be misclassified as a VHD and then fail to open.
+                let disk_type = be_u32(&footer[60..64]);
+                let plausible = if disk_type == 2 {
+                    let current_size = be_u64(&footer[48..56]);
+                    current_size
+                        .checked_add((VHD_FOOTER_SIZE as u64) * 2)
+                        .is_some_and(|required| len >= required)
+                } else {
+                    true
+                };
+                if plausible {
+                    candidates.push(candidate(DiskFormat::Vhd, DetectionConfidence::Signature));
                 }
             }
-
-            // For a truncated AeroSparse/VHD file, treat the image as the corresponding format so
-            // the subsequent open reports corruption instead of silently falling back to raw.
-            if first8 == AEROSPAR_MAGIC {
-                return Ok(DiskFormat::AeroSparse);
-            }
-            if first8 == VHD_COOKIE {
-                return Ok(DiskFormat::Vhd);
-            }
         }
     }
 
     // VHD fixed disks have only a footer at the end; dynamic disks typically have a footer at
     // both the beginning and the end. Check both.
-    if len >= VHD_FOOTER_SIZE as u64 {
+    if len >= VHD_FOOTER_SIZE as u64 && !candidates.iter().any(|c| c.format == DiskFormat::Vhd) {
         let mut cookie = [0u8; 8];
         backend.read_at(len - VHD_FOOTER_SIZE as u64, &mut cookie)?;
         if cookie == VHD_COOKIE {
             let mut footer = [0u8; VHD_FOOTER_SIZE];
             backend.read_at(len - VHD_FOOTER_SIZE as u64, &mut footer)?;
             if looks_like_vhd_footer(&footer, len) {
-                return Ok(DiskFormat::Vhd);
+                candidates.push(candidate(DiskFormat::Vhd, DetectionConfidence::Signature));
             }
         }
     }
 
-    Ok(DiskFormat::Raw)
+    candidates.push(candidate(DiskFormat::Raw, DetectionConfidence::Fallback));
+    Ok(candidates)
+}
+
+fn candidate(format: DiskFormat, confidence: DetectionConfidence) -> FormatCandidate {
+    FormatCandidate { format, confidence }
 }
 
 fn looks_like_vhd_footer(footer: &[u8; VHD_FOOTER_SIZE], file_len: u64) -> bool {
@@ -221,6 +241,37 @@ fn be_u64(bytes: &[u8]) -> u64 {
     ])
 }
 
+/// Whether an image of `format` declares a parent disk: a QCOW2 backing file or a VHD differencing
+/// disk.
+///
+/// Truncated headers report `false`; opening the image reports the structured error.
+fn references_parent<B: StorageBackend>(format: DiskFormat, backend: &mut B) -> Result<bool> {
+    let len = backend.len()?;
+    match format {
+        DiskFormat::Qcow2 => {
+            // Peek at the backing file fields (8..20).
+            if len < 20 {
+                return Ok(false);
+            }
+            let mut header = [0u8; 20];
+            backend.read_at(0, &mut header)?;
+            let backing_file_offset = be_u64(&header[8..16]);
+            let backing_file_size = be_u32(&header[16..20]);
+            Ok(backing_file_offset != 0 || backing_file_size != 0)
+        }
+        DiskFormat::Vhd => {
+            // VHD footer is always 512 bytes and the disk type is stored at 60..64.
+            if len < VHD_FOOTER_SIZE as u64 {
+                return Ok(false);
+            }
+            let mut footer = [0u8; VHD_FOOTER_SIZE];
+            backend.read_at(len - VHD_FOOTER_SIZE as u64, &mut footer)?;
+            Ok(be_u32(&footer[60..64]) == 4)
+        }
+        DiskFormat::Raw | DiskFormat::AeroSparse => Ok(false),
+    }
+}
+
 /// A convenience wrapper that can open multiple disk image formats from a single backend.
 pub enum DiskImage<B> {
     Raw(RawDisk<B>),
@@ -283,45 +334,59 @@ impl<B: StorageBackend> DiskImage<B> {
         parent: Box<dyn crate::VirtualDisk>,
     ) -> Result<Self> {
         let format = detect_format(&mut backend)?;
+        if references_parent(format, &mut backend)? {
+            Self::open_with_parent(format, backend, parent)
+        } else {
+            let _ = parent;
+            Self::open_with_format(format, backend)
+        }
+    }
 
-        match format {
-            DiskFormat::Qcow2 => {
-                // Peek at the backing file fields (8..20) to decide whether to use the parent.
-                // If the header is truncated, `open_with_format` will report a structured error.
-                let len = backend.len()?;
-                if len >= 20 {
-                    let mut header = [0u8; 20];
-                    backend.read_at(0, &mut header)?;
-                    let backing_file_offset = be_u64(&header[8..16]);
-                    let backing_file_size = be_u32(&header[16..20]);
-                    if backing_file_offset != 0 || backing_file_size != 0 {
-                        return Self::open_with_parent(format, backend, parent);
+    /// Open a stack of disk layers as a single disk.
+    ///
+    /// `layers[0]` is the base image; each subsequent layer is an overlay on top of everything
+    /// below it:
+    ///
+    /// - QCOW2 images with a backing file and VHD differencing disks use their native parent
+    ///   mechanism (the backing file name stored in the image is ignored).
+    /// - AeroSparse overlays use [`AeroCowDisk`] semantics and must match the capacity of the
+    ///   layers below.
+    ///
+    /// Raw images and QCOW2/VHD images without a parent reference can only be the base layer.
+    /// Formats are taken as given (no detection); use [`detect_format`] beforehand if needed.
+    ///
+    /// This lets hosts compose, for example, a streamed base image with a local OPFS overlay.
+    pub fn open_layered(layers: Vec<(B, DiskFormat)>) -> Result<Box<dyn crate::VirtualDisk>>
+    where
+        B: crate::disk::VirtualDiskSend + 'static,
+    {
+        let mut layers = layers.into_iter();
+        let (base_backend, base_format) = layers.next().ok_or(DiskError::InvalidConfig(
+            "layered disk requires at least one layer",
+        ))?;
+        let mut disk: Box<dyn crate::VirtualDisk> =
+            Box::new(Self::open_with_format(base_format, base_backend)?);
+
+        for (mut backend, format) in layers {
+            disk = match format {
+                DiskFormat::AeroSparse => Box::new(AeroCowDisk::open(disk, backend)?),
+                DiskFormat::Qcow2 | DiskFormat::Vhd => {
+                    if !references_parent(format, &mut backend)? {
+                        return Err(DiskError::InvalidConfig(
+                            "overlay qcow2/vhd layer has no backing file or parent",
+                        ));
                     }
+                    Box::new(Self::open_with_parent(format, backend, disk)?)
                 }
-                let _ = parent;
-                Self::open_with_format(format, backend)
-            }
-            DiskFormat::Vhd => {
-                // VHD footer is always 512 bytes and the disk type is stored at 60..64.
-                // If the image is truncated, `open_with_format` will report the appropriate error.
-                let len = backend.len()?;
-                if len >= VHD_FOOTER_SIZE as u64 {
-                    let footer_offset = len - VHD_FOOTER_SIZE as u64;
-                    let mut footer = [0u8; VHD_FOOTER_SIZE];
-                    backend.read_at(footer_offset, &mut footer)?;
-                    let disk_type = be_u32(&footer[60..64]);
-                    if disk_type == 4 {
-                        return Self::open_with_parent(format, backend, parent);
-                    }
+                DiskFormat::Raw => {
+                    return Err(DiskError::InvalidConfig(
+                        "raw images can only be used as the base layer",
+                    ))
                 }
-                let _ = parent;
-                Self::open_with_format(format, backend)
-            }
-            _ => {
-                let _ = parent;
-                Self::open_with_format(format, backend)
-            }
+            };
         }
+
+        Ok(disk)
     }
 
     pub fn into_backend(self) -> B {
@@ -371,6 +436,15 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> crate::VirtualDisk for Di
         }
     }
 
+    fn write_barrier(&mut self) -> Result<()> {
+        match self {
+            Self::Raw(d) => d.write_barrier(),
+            Self::AeroSparse(d) => d.write_barrier(),
+            Self::Qcow2(d) => d.write_barrier(),
+            Self::Vhd(d) => d.write_barrier(),
+        }
+    }
+
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
         match self {
             Self::Raw(d) => d.discard_range(offset, len),
@@ -379,4 +453,73 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> crate::VirtualDisk for Di
             Self::Vhd(d) => d.discard_range(offset, len),
         }
     }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        match self {
+            Self::Raw(d) => d.resize(new_capacity),
+            Self::AeroSparse(d) => d.resize(new_capacity),
+            Self::Qcow2(d) => d.resize(new_capacity),
+            Self::Vhd(d) => d.resize(new_capacity),
+        }
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        match self {
+            Self::Raw(d) => d.allocation_status(offset, len),
+            Self::AeroSparse(d) => d.allocation_status(offset, len),
+            Self::Qcow2(d) => d.allocation_status(offset, len),
+            Self::Vhd(d) => d.allocation_status(offset, len),
+        }
+    }
+}
+
+/// Copy every byte of `src` into `dst` (e.g. to convert an image between formats), then flush
+/// `dst`.
+///
+/// `dst` must be at least as large as `src`. With `skip_unallocated`, ranges that `src` reports as
+/// [`AllocationStatus::Zero`] or [`AllocationStatus::Unallocated`] (see
+/// [`VirtualDisk::allocation_status`]) are neither read nor written, so a sparse destination stays
+/// sparse there; `dst` must then already read as zeros in those ranges, as a freshly created
+/// image does.
+pub fn copy_disk<S, D>(src: &mut S, dst: &mut D, skip_unallocated: bool) -> Result<()>
+where
+    S: VirtualDisk + ?Sized,
+    D: VirtualDisk + ?Sized,
+{
+    let capacity = src.capacity_bytes();
+    if dst.capacity_bytes() < capacity {
+        return Err(DiskError::InvalidConfig(
+            "copy destination is smaller than the source disk",
+        ));
+    }
+
+    let mut buf = vec![0u8; COPY_BUF_BYTES];
+    let mut window = 0u64;
+    while window < capacity {
+        let window_len = (capacity - window).min(COPY_STATUS_WINDOW_BYTES);
+        let ranges = if skip_unallocated {
+            src.allocation_status(window, window_len)?
+        } else {
+            vec![AllocatedRange {
+                offset: window,
+                len: window_len,
+                status: AllocationStatus::Data,
+            }]
+        };
+        for range in ranges {
+            if range.status != AllocationStatus::Data {
+                continue;
+            }
+            let end = range.offset + range.len;
+            let mut off = range.offset;
+            while off < end {
+                let n = (end - off).min(COPY_BUF_BYTES as u64) as usize;
+                src.read_at(off, &mut buf[..n])?;
+                dst.write_at(off, &buf[..n])?;
+                off += n as u64;
+            }
+        }
+        window += window_len;
+    }
+    dst.flush()
 }
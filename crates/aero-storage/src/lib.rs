@@ -7,10 +7,17 @@
 //! - [`RawDisk`]: maps a resizable byte backend to a fixed-capacity disk (raw images)
 //! - [`AeroSparseDisk`]: Aero-specific sparse disk format for huge virtual disks
 //! - [`Qcow2Disk`]: QCOW2 v2/v3 (subset) support for common developer images
-//! - [`VhdDisk`]: VHD fixed/dynamic + differencing (explicit parent) support
+//! - [`VhdDisk`]: VHD fixed/dynamic + differencing (explicit parent) support, plus
+//!   [`VhdDisk::validate`] / [`VhdDisk::repair`] for damaged images
 //! - [`AeroCowDisk`]: copy-on-write overlay on top of a base disk
 //! - [`BlockCachedDisk`]: LRU, write-back block cache wrapper
+//! - [`MemBackend`]: in-memory backend with copy-on-write [`MemBackend::fork`] and immutable
+//!   [`FrozenBackend`] bases for test fixtures
 //! - [`DiskImage`]: auto-detect + open wrapper for multiple formats
+//! - [`copy_disk`]: image conversion that can skip ranges reported unallocated by
+//!   [`VirtualDisk::allocation_status`]
+//! - [`convert::convert_image`]: format conversion with progress reporting, cancellation and verification
+//! - [`StorageFaultInjector`]: deterministic latency/fault injection for storage controllers
 //!
 //! ## Example: open with format detection
 //!
@@ -44,6 +51,9 @@
 //! # }
 //! ```
 //!
+//! For very large images, [`MmapFileBackend`] memory-maps the file instead (falling back to
+//! positional I/O where mapping is unavailable), so reads and writes avoid a syscall per access.
+//!
 //! In the browser, local persistence is typically backed by OPFS. Aero provides a
 //! Rust/wasm32 OPFS backend implementation in `crates/aero-opfs` that implements
 //! [`StorageBackend`] (byte-addressed) and [`VirtualDisk`] (disk-oriented).
@@ -64,14 +74,23 @@
 //! Fallible operations return [`Result`], which uses the unified [`DiskError`] type. `DiskError`
 //! is shared across both native and wasm32 backends (including `crates/aero-opfs`), and its
 //! [`DiskError::Io`] variant intentionally stores a human-readable `String` so browser backends
-//! can surface JavaScript/DOM errors without requiring `std::io::Error`.
+//! can surface JavaScript/DOM errors without requiring `std::io::Error`; native file backends use
+//! [`DiskError::NativeIo`], which keeps the `std::io::Error` as its source.
+//!
+//! Errors coming out of a wrapped disk or backend are tagged with a [`DiskError::Context`] per
+//! layer (which [`DiskLayer`], and the offset/length it was accessing), so the `Display` output
+//! shows the full path down the stack. Match on [`DiskError::root`] to inspect the cause.
 
 mod backend;
 mod cache;
+pub mod convert;
 mod cow;
 mod disk;
 mod error;
+mod fault;
 mod formats;
+#[cfg(not(target_arch = "wasm32"))]
+mod mmap;
 mod qcow2;
 mod sparse;
 mod util;
@@ -81,15 +100,35 @@ mod vhd;
 pub use backend::FileBackend;
 #[cfg(not(target_arch = "wasm32"))]
 pub use backend::StdFileBackend;
-pub use backend::{MemBackend, ReadOnlyBackend, StorageBackend};
-pub use cache::{BlockCacheStats, BlockCachedDisk};
+pub use backend::{
+    FrozenBackend, MemBackend, ReadOnlyBackend, StorageBackend, MEM_BACKEND_PAGE_SIZE,
+};
+pub use cache::{BlockCacheStats, BlockCachedDisk, DEFAULT_FLUSH_BATCH_WINDOW};
 pub use cow::AeroCowDisk;
-pub use disk::{RawDisk, ReadOnlyDisk, VirtualDisk, VirtualDiskSend, SECTOR_SIZE};
-pub use error::{DiskError, Result};
-pub use formats::{detect_format, DiskFormat, DiskImage};
-pub use qcow2::Qcow2Disk;
-pub use sparse::{AeroSparseConfig, AeroSparseDisk, AeroSparseHeader};
-pub use vhd::VhdDisk;
+pub use disk::{
+    AllocatedRange, AllocationStatus, RawDisk, ReadOnlyDisk, VirtualDisk, VirtualDiskSend,
+    SECTOR_SIZE,
+};
+pub use error::{DiskError, DiskErrorContext, DiskLayer, IoSource, Result};
+pub use fault::{
+    StorageFaultCounters, StorageFaultDelay, StorageFaultError, StorageFaultInjector,
+    StorageFaultOutcome, StorageFaultPolicy, StorageRequest, StorageRequestKind,
+};
+pub use formats::{
+    copy_disk, detect_format, detect_format_candidates, DetectionConfidence, DiskFormat, DiskImage,
+    FormatCandidate,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use mmap::MmapFileBackend;
+pub use qcow2::{Qcow2BackingFileInfo, Qcow2Disk};
+pub use sparse::{
+    AeroSparseConfig, AeroSparseDisk, AeroSparseHeader, ChecksumMismatchPolicy, ScrubReport,
+};
+pub use vhd::{
+    VhdDifferencingOptions, VhdDisk, VhdFooterLocation, VhdIdentity, VhdParentInfo,
+    VhdParentLocator, VhdProblem, VhdRepairAction, VhdRepairPolicy, VhdRepairReport,
+    VhdValidationReport, VHD_MAX_REPORTED_BAT_PROBLEMS,
+};
 
 #[cfg(test)]
 mod tests;
@@ -108,10 +147,11 @@ pub use chunked_streaming::{
     ChunkedStreamingTelemetrySnapshot,
 };
 #[cfg(not(target_arch = "wasm32"))]
-pub use range_set::{ByteRange, RangeSet};
+pub use range_set::{ByteRange, Gaps, RangeSet};
 #[cfg(not(target_arch = "wasm32"))]
 pub use streaming::{
-    CacheStatus, ChunkManifest, ChunkStore, DirectoryChunkStore, SparseFileChunkStore,
-    StreamingCacheBackend, StreamingDisk, StreamingDiskConfig, StreamingDiskError,
-    StreamingDiskOptions, StreamingTelemetrySnapshot, DEFAULT_CHUNK_SIZE, DEFAULT_SECTOR_SIZE,
+    CacheStatus, CacheVerifyReport, ChunkHash, ChunkHashAlgorithm, ChunkManifest, ChunkStore,
+    DirectoryChunkStore, SparseFileChunkStore, StreamingAvailabilityPolicy, StreamingCacheBackend,
+    StreamingDisk, StreamingDiskConfig, StreamingDiskError, StreamingDiskOptions,
+    StreamingTelemetrySnapshot, DEFAULT_CHUNK_SIZE, DEFAULT_SECTOR_SIZE,
 };
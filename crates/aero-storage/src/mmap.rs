@@ -0,0 +1,293 @@
+use crate::{DiskError, Result, StdFileBackend, StorageBackend};
+
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Native memory-mapped file storage backend.
+///
+/// Intended for host-side tooling and integration tests that work with large (multi-GiB) raw
+/// images: reads and writes are plain memory copies against a shared mapping of the file, so
+/// nothing is copied up front and only touched pages are faulted in.
+///
+/// - [`StorageBackend::set_len`] resizes the file and remaps it; writes past the end grow the file
+///   first.
+/// - [`StorageBackend::flush`] writes dirty pages back with `msync(MS_SYNC)` followed by
+///   [`File::sync_all`]. Dropping the backend flushes as well (errors are ignored).
+/// - When opened read-only, [`StorageBackend::write_at`] and [`StorageBackend::set_len`] return
+///   [`DiskError::NotSupported`] ("read-only backend"), matching [`StdFileBackend`].
+///
+/// Mapping is only implemented on Unix. On other platforms, or when the file cannot be mapped
+/// (e.g. it is larger than the address space or lives on a filesystem without mmap support), the
+/// backend transparently falls back to positional I/O through [`StdFileBackend`].
+///
+/// The file must not be truncated by another process while mapped: accessing pages beyond the
+/// new end of file faults (`SIGBUS`).
+#[derive(Debug)]
+pub struct MmapFileBackend {
+    file: StdFileBackend,
+    len: u64,
+    map: Option<Mapping>,
+}
+
+impl MmapFileBackend {
+    /// Open an existing file.
+    pub fn open<P: AsRef<Path>>(path: P, read_only: bool) -> Result<Self> {
+        Self::from_std(StdFileBackend::open(path, read_only)?)
+    }
+
+    /// Open an existing file read-only.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, true)
+    }
+
+    /// Open an existing file for reading and writing.
+    pub fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open(path, false)
+    }
+
+    /// Create/truncate a file and set its length to `size` bytes.
+    pub fn create<P: AsRef<Path>>(path: P, size: u64) -> Result<Self> {
+        let path_ref = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path_ref)
+            .map_err(|e| {
+                DiskError::native_io(
+                    format!(
+                        "failed to create file (path={} size={})",
+                        path_ref.display(),
+                        size
+                    ),
+                    e,
+                )
+            })?;
+        let mut backend = Self::from_std(StdFileBackend::from_file_with_path(file, path_ref))?;
+        backend.set_len(size)?;
+        Ok(backend)
+    }
+
+    /// Wrap an already-open [`std::fs::File`].
+    ///
+    /// The file must have been opened for writing unless `read_only` is set.
+    pub fn from_file(file: File, read_only: bool) -> Result<Self> {
+        Self::from_std(StdFileBackend::from_file(file).with_read_only(read_only))
+    }
+
+    fn from_std(mut file: StdFileBackend) -> Result<Self> {
+        let len = file.len()?;
+        let mut backend = Self {
+            file,
+            len,
+            map: None,
+        };
+        backend.remap();
+        Ok(backend)
+    }
+
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.file.is_read_only()
+    }
+
+    /// Whether I/O currently goes through a memory mapping (as opposed to the positional I/O
+    /// fallback).
+    #[must_use]
+    pub fn is_mapped(&self) -> bool {
+        self.map.is_some()
+    }
+
+    fn remap(&mut self) {
+        // Unmap first so the old and new mappings never coexist.
+        self.map = None;
+        self.map = Mapping::new(self.file.file(), self.len, self.is_read_only());
+    }
+
+    fn sync_mapping(&self) -> Result<()> {
+        match &self.map {
+            Some(map) => map
+                .sync()
+                .map_err(|e| DiskError::native_io("msync failed", e)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_bounds(&self, offset: u64, len: usize) -> Result<()> {
+        let len_u64 = u64::try_from(len).map_err(|_| DiskError::OffsetOverflow)?;
+        let end = offset
+            .checked_add(len_u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        if end > self.len {
+            return Err(DiskError::OutOfBounds {
+                offset,
+                len,
+                capacity: self.len,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for MmapFileBackend {
+    fn len(&mut self) -> Result<u64> {
+        Ok(self.len)
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        if self.is_read_only() {
+            return Err(DiskError::NotSupported("read-only backend".to_string()));
+        }
+        if len == self.len {
+            return Ok(());
+        }
+        // Write back dirty pages before the mapping goes away (shrinking discards the tail).
+        self.sync_mapping()?;
+        self.map = None;
+        let res = self.file.set_len(len);
+        if res.is_ok() {
+            self.len = len;
+        }
+        self.remap();
+        res
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.check_bounds(offset, buf.len())?;
+        match &self.map {
+            Some(map) => {
+                // In bounds of the mapping, so the offset fits in `usize`.
+                let start = offset as usize;
+                buf.copy_from_slice(&map.as_slice()[start..start + buf.len()]);
+                Ok(())
+            }
+            None => self.file.read_at(offset, buf),
+        }
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        if self.is_read_only() {
+            return Err(DiskError::NotSupported("read-only backend".to_string()));
+        }
+        let len_u64 = u64::try_from(buf.len()).map_err(|_| DiskError::OffsetOverflow)?;
+        let end = offset
+            .checked_add(len_u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        if end > self.len {
+            self.set_len(end)?;
+        }
+
+        match &mut self.map {
+            Some(map) => {
+                let start = offset as usize;
+                map.as_mut_slice()[start..start + buf.len()].copy_from_slice(buf);
+                Ok(())
+            }
+            None => self.file.write_at(offset, buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
+        self.sync_mapping()?;
+        self.file.flush()
+    }
+}
+
+impl Drop for MmapFileBackend {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// A shared mapping of an entire file.
+#[derive(Debug)]
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    /// Maps the first `len` bytes of `file`, or returns `None` if the file cannot (or need not)
+    /// be mapped.
+    #[cfg(unix)]
+    fn new(file: &File, len: u64, read_only: bool) -> Option<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        // Zero-length mappings are invalid; an empty file has nothing to map anyway.
+        let len = usize::try_from(len).ok().filter(|&len| len != 0)?;
+        let prot = if read_only {
+            libc::PROT_READ
+        } else {
+            libc::PROT_READ | libc::PROT_WRITE
+        };
+        // SAFETY: mapping a file descriptor we own with a non-zero length; the result is checked
+        // against `MAP_FAILED` before use.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        Some(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new(_file: &File, _len: u64, _read_only: bool) -> Option<Self> {
+        None
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: `ptr` points to a live mapping of `len` bytes for the lifetime of `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: as above; writable mappings are only created for read-write backends, and
+        // callers only write through read-write backends.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+
+    #[cfg(unix)]
+    fn sync(&self) -> std::io::Result<()> {
+        // SAFETY: `ptr`/`len` describe a live, page-aligned mapping.
+        let rc = unsafe { libc::msync(self.ptr.cast(), self.len, libc::MS_SYNC) };
+        if rc == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn sync(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: `ptr`/`len` describe a live mapping created by `mmap` that is not used again.
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+// SAFETY: the mapping is uniquely owned and only accessed through `&self`/`&mut self`, like a
+// heap buffer.
+unsafe impl Send for Mapping {}
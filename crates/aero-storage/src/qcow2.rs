@@ -1,18 +1,33 @@
 use lru::LruCache;
 use std::num::NonZeroUsize;
 
+use crate::disk::push_allocated_range;
 use crate::util::align_up_u64;
 use crate::util::checked_range;
-use crate::{DiskError, Result, StorageBackend, VirtualDisk, SECTOR_SIZE};
+use crate::{
+    AllocatedRange, AllocationStatus, DiskError, DiskLayer, Result, StorageBackend, VirtualDisk,
+    SECTOR_SIZE,
+};
 
 const QCOW2_MAGIC: [u8; 4] = *b"QFI\xfb";
 
 // QCOW2 is a big-endian on-disk format.
 const QCOW2_OFLAG_COPIED: u64 = 1 << 63;
 const QCOW2_OFLAG_COMPRESSED: u64 = 1 << 62;
-// "Zero cluster" flag (introduced in qcow2 v3). Treat as unallocated.
+// "Zero cluster" flag (introduced in qcow2 v3). Reads as zeros and never falls through to the
+// backing file.
 const QCOW2_OFLAG_ZERO: u64 = 1 << 0;
 
+// Header extensions (between the header and the backing file name).
+const QCOW2_EXT_END: u32 = 0;
+const QCOW2_EXT_BACKING_FORMAT: u32 = 0xE279_2ACA;
+
+// QEMU refuses backing file names longer than this.
+const QCOW2_MAX_BACKING_FILE_NAME: u32 = 1023;
+
+// Maximum number of backing files followed by `Qcow2Disk::open_with_resolver` (also breaks cycles).
+const QCOW2_MAX_BACKING_CHAIN_DEPTH: usize = 16;
+
 // Hard cap to avoid absurd allocations when parsing untrusted images.
 const MAX_TABLE_BYTES: u64 = 128 * 1024 * 1024; // 128 MiB
 
@@ -32,8 +47,7 @@ struct Qcow2Header {
     cluster_bits: u32,
     size: u64,
     header_length: u32,
-    backing_file_offset: u64,
-    backing_file_size: u32,
+    backing: Option<Qcow2BackingFileInfo>,
     l1_entries: u64,
     l1_table_offset: u64,
     refcount_table_offset: u64,
@@ -118,6 +132,9 @@ impl Qcow2Header {
                     "qcow2 backing file overlaps header",
                 ));
             }
+            if backing_file_size > QCOW2_MAX_BACKING_FILE_NAME {
+                return Err(DiskError::CorruptImage("qcow2 backing file name too long"));
+            }
             let end = backing_file_offset
                 .checked_add(backing_file_size as u64)
                 .ok_or(DiskError::OffsetOverflow)?;
@@ -182,12 +199,25 @@ impl Qcow2Header {
             return Err(DiskError::Unsupported("qcow2 l1 table too large"));
         }
 
+        let backing = if backing_file_offset != 0 {
+            let mut name = vec![0u8; backing_file_size as usize];
+            backend.read_at(backing_file_offset, &mut name)?;
+            let file_name = String::from_utf8(name)
+                .map_err(|_| DiskError::CorruptImage("qcow2 backing file name is not utf-8"))?;
+            // Header extensions end at the backing file name (QEMU stores it right after them)
+            // and never leave the first cluster.
+            let ext_end = backing_file_offset.min(cluster_size).min(len);
+            let format = parse_backing_format_extension(backend, header_length_u64, ext_end)?;
+            Some(Qcow2BackingFileInfo { file_name, format })
+        } else {
+            None
+        };
+
         Ok(Self {
             cluster_bits,
             size,
             header_length,
-            backing_file_offset,
-            backing_file_size,
+            backing,
             l1_entries: required_l1,
             l1_table_offset,
             refcount_table_offset,
@@ -200,13 +230,124 @@ impl Qcow2Header {
     }
 }
 
+/// Backing file reference recorded in a QCOW2 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Qcow2BackingFileInfo {
+    /// Backing file name exactly as stored in the image (usually relative to the overlay).
+    pub file_name: String,
+    /// Backing format from the header extension (e.g. `"qcow2"`, `"raw"`), if recorded.
+    pub format: Option<String>,
+}
+
+fn parse_backing_format_extension<B: StorageBackend>(
+    backend: &mut B,
+    start: u64,
+    end: u64,
+) -> Result<Option<String>> {
+    let mut format = None;
+    let mut off = start;
+    while off + 8 <= end {
+        let mut ext = [0u8; 8];
+        backend.read_at(off, &mut ext)?;
+        let ext_type = be_u32(&ext[0..4]);
+        let ext_len = be_u32(&ext[4..8]) as u64;
+        if ext_type == QCOW2_EXT_END {
+            break;
+        }
+        let data_off = off + 8;
+        if ext_len > end - data_off {
+            return Err(DiskError::CorruptImage("qcow2 header extension truncated"));
+        }
+        if ext_type == QCOW2_EXT_BACKING_FORMAT {
+            let mut data = vec![0u8; ext_len as usize];
+            backend.read_at(data_off, &mut data)?;
+            let name = String::from_utf8(data)
+                .map_err(|_| DiskError::CorruptImage("qcow2 backing format is not utf-8"))?;
+            format = Some(name);
+        }
+        // Extension data is padded to a multiple of 8 bytes.
+        off = data_off + align_up_u64(ext_len, 8)?;
+    }
+    Ok(format)
+}
+
+/// Read-only view of a resolved backing disk as a [`StorageBackend`], so a QCOW2 backing file can
+/// itself be opened as a [`Qcow2Disk`].
+struct BackingFileBackend(Box<dyn VirtualDisk>);
+
+impl StorageBackend for BackingFileBackend {
+    fn len(&mut self) -> Result<u64> {
+        Ok(self.0.capacity_bytes())
+    }
+
+    fn set_len(&mut self, _len: u64) -> Result<()> {
+        Err(DiskError::NotSupported("read-only".into()))
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.0.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, _offset: u64, _buf: &[u8]) -> Result<()> {
+        Err(DiskError::NotSupported("read-only".into()))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Open the backing file `info` (the `depth`-th level of the chain) through `resolver`.
+fn open_backing_file(
+    info: &Qcow2BackingFileInfo,
+    resolver: &mut dyn FnMut(&str) -> Result<Box<dyn VirtualDisk>>,
+    depth: usize,
+) -> Result<Box<dyn VirtualDisk>> {
+    if depth > QCOW2_MAX_BACKING_CHAIN_DEPTH {
+        return Err(DiskError::Unsupported("qcow2 backing chain too deep"));
+    }
+    let mut disk = resolver(&info.file_name)?;
+    let is_qcow2 = match info.format.as_deref() {
+        Some("qcow2") => true,
+        Some("raw") => false,
+        Some(_) => return Err(DiskError::Unsupported("qcow2 backing file format")),
+        None => {
+            let mut magic = [0u8; 4];
+            if disk.capacity_bytes() < magic.len() as u64 {
+                false
+            } else {
+                disk.read_at(0, &mut magic)?;
+                magic == QCOW2_MAGIC
+            }
+        }
+    };
+    if !is_qcow2 {
+        return Ok(disk);
+    }
+    let backing = Qcow2Disk::open_resolved(BackingFileBackend(disk), resolver, depth)?;
+    Ok(Box::new(backing))
+}
+
+/// Where the contents of a guest cluster come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClusterSource {
+    /// Allocated at this physical offset in the image.
+    Data(u64),
+    /// QCOW2 zero cluster.
+    Zero,
+    /// Not allocated in this image: the backing file (or zeros without one).
+    Backing,
+}
+
 /// QCOW2 v2/v3 disk image (subset).
 ///
 /// Supported:
 /// - unencrypted
 /// - uncompressed
-/// - backing file *only* via [`Qcow2Disk::open_with_parent`] (explicit parent disk injection;
-///   backing file paths are not resolved automatically)
+/// - backing files, either injected explicitly ([`Qcow2Disk::open_with_parent`],
+///   [`Qcow2Disk::set_backing`]) or resolved by name ([`Qcow2Disk::open_with_resolver`]). Reads
+///   past the end of a smaller backing file return zeros.
+/// - zero clusters (read as zeros without consulting the backing file)
 /// - no internal snapshots
 /// - copy-on-write for shared (refcount>1) data clusters
 pub struct Qcow2Disk<B> {
@@ -231,27 +372,67 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         Self::open_parsed(backend, header, None)
     }
 
+    /// Open an image that declares a backing file, using `parent` as the backing disk.
+    ///
+    /// `parent` may be smaller than the image; reads beyond its end return zeros.
     pub fn open_with_parent(mut backend: B, parent: Box<dyn VirtualDisk>) -> Result<Self> {
         let header = Qcow2Header::parse(&mut backend, true)?;
-        if header.backing_file_offset == 0 || header.backing_file_size == 0 {
+        if header.backing.is_none() {
             return Err(DiskError::InvalidConfig(
                 "qcow2 image does not declare a backing file",
             ));
         }
-        if parent.capacity_bytes() != header.size {
-            return Err(DiskError::InvalidConfig(
-                "qcow2 parent capacity does not match image size",
-            ));
-        }
 
         Self::open_parsed(backend, header, Some(parent))
     }
 
+    /// Open an image and its whole backing chain.
+    ///
+    /// `resolver` is called with each backing file name (see [`Qcow2BackingFileInfo`]) and
+    /// returns that file's raw contents, e.g. a [`crate::RawDisk`] over the opened file. Backing
+    /// files recorded (or detected) as qcow2 are opened recursively; `raw` backing files are used
+    /// as returned. Chains deeper than 16 levels, including cycles, are rejected.
+    pub fn open_with_resolver(
+        backend: B,
+        resolver: &mut dyn FnMut(&str) -> Result<Box<dyn VirtualDisk>>,
+    ) -> Result<Self> {
+        Self::open_resolved(backend, resolver, 0)
+    }
+
+    fn open_resolved(
+        mut backend: B,
+        resolver: &mut dyn FnMut(&str) -> Result<Box<dyn VirtualDisk>>,
+        depth: usize,
+    ) -> Result<Self> {
+        let header = Qcow2Header::parse(&mut backend, true)?;
+        let backing = match header.backing.as_ref() {
+            Some(info) => Some(open_backing_file(info, resolver, depth + 1)?),
+            None => None,
+        };
+        Self::open_parsed(backend, header, backing)
+    }
+
     pub fn open_with_backing(backend: B, backing: Box<dyn VirtualDisk>) -> Result<Self> {
         // Backwards-compatible alias for older call sites/tests.
         Self::open_with_parent(backend, backing)
     }
 
+    /// Backing file recorded in the image header, if any.
+    pub fn backing_file_info(&self) -> Option<&Qcow2BackingFileInfo> {
+        self.header.backing.as_ref()
+    }
+
+    /// Attach (or replace) the backing disk of an image that declares a backing file.
+    pub fn set_backing(&mut self, backing: Box<dyn VirtualDisk>) -> Result<()> {
+        if self.header.backing.is_none() {
+            return Err(DiskError::InvalidConfig(
+                "qcow2 image does not declare a backing file",
+            ));
+        }
+        self.backing = Some(backing);
+        Ok(())
+    }
+
     pub fn into_backend(self) -> B {
         self.backend
     }
@@ -424,19 +605,29 @@ impl<B: StorageBackend> Qcow2Disk<B> {
     }
 
     fn backend_read_at(&mut self, offset: u64, buf: &mut [u8], ctx: &'static str) -> Result<()> {
+        let len = buf.len();
         match self.backend.read_at(offset, buf) {
             Ok(()) => Ok(()),
             Err(DiskError::OutOfBounds { .. }) => Err(DiskError::CorruptImage(ctx)),
-            Err(e) => Err(e),
+            Err(e) => Err(e.with_context(DiskLayer::Qcow2, offset, len)),
         }
     }
 
+    fn backend_write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        self.backend
+            .write_at(offset, buf)
+            .map_err(|e| e.with_context(DiskLayer::Qcow2, offset, buf.len()))
+    }
+
     fn validate_cluster_present(&mut self, cluster_offset: u64, ctx: &'static str) -> Result<()> {
         let cluster_size = self.cluster_size();
         let end = cluster_offset
             .checked_add(cluster_size)
             .ok_or(DiskError::OffsetOverflow)?;
-        let len = self.backend.len()?;
+        let len = self
+            .backend
+            .len()
+            .map_err(|e| e.in_layer(DiskLayer::Qcow2))?;
         if end > len {
             return Err(DiskError::CorruptImage(ctx));
         }
@@ -713,21 +904,58 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         Ok(())
     }
 
-    fn lookup_data_cluster(&mut self, guest_cluster_index: u64) -> Result<Option<u64>> {
+    /// Raw L2 entry for a guest cluster (`0` when its L2 table is unallocated).
+    fn lookup_l2_entry(&mut self, guest_cluster_index: u64) -> Result<u64> {
         let (l1_index, l2_index) = self.l1_l2_index(guest_cluster_index)?;
         let l1_entry = self.l1_table[l1_index];
         let Some(l2_offset) = self.l2_table_offset_from_l1_entry(l1_entry)? else {
-            return Ok(None);
+            return Ok(0);
         };
         self.ensure_l2_cached(l2_offset)?;
         let table = self
             .l2_cache
             .get(&l2_offset)
             .ok_or(DiskError::CorruptImage("qcow2 l2 cache missing"))?;
-        let l2_entry = *table
+        table
             .get(l2_index)
-            .ok_or(DiskError::CorruptImage("qcow2 l2 index out of range"))?;
-        self.data_cluster_offset_from_l2_entry(l2_entry)
+            .copied()
+            .ok_or(DiskError::CorruptImage("qcow2 l2 index out of range"))
+    }
+
+    fn cluster_source(&mut self, guest_cluster_index: u64) -> Result<ClusterSource> {
+        let l2_entry = self.lookup_l2_entry(guest_cluster_index)?;
+        if let Some(offset) = self.data_cluster_offset_from_l2_entry(l2_entry)? {
+            Ok(ClusterSource::Data(offset))
+        } else if (l2_entry & QCOW2_OFLAG_ZERO) != 0 {
+            Ok(ClusterSource::Zero)
+        } else {
+            Ok(ClusterSource::Backing)
+        }
+    }
+
+    fn cluster_status(&mut self, guest_cluster_index: u64) -> Result<AllocationStatus> {
+        Ok(match self.cluster_source(guest_cluster_index)? {
+            ClusterSource::Data(_) => AllocationStatus::Data,
+            ClusterSource::Zero => AllocationStatus::Zero,
+            ClusterSource::Backing => AllocationStatus::Unallocated,
+        })
+    }
+
+    /// Read guest bytes from the backing disk; bytes past its end (or without one) read as zero.
+    fn read_backing(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        let Some(backing) = self.backing.as_mut() else {
+            buf.fill(0);
+            return Ok(());
+        };
+        let avail = backing.capacity_bytes().saturating_sub(offset);
+        let n = usize::try_from(avail).unwrap_or(usize::MAX).min(buf.len());
+        if n > 0 {
+            backing
+                .read_at(offset, &mut buf[..n])
+                .map_err(|e| e.with_context(DiskLayer::Qcow2, offset, n))?;
+        }
+        buf[n..].fill(0);
+        Ok(())
     }
 
     fn set_l2_entry(&mut self, l2_offset: u64, l2_index: usize, entry: u64) -> Result<()> {
@@ -744,7 +972,7 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         let offset = l2_offset
             .checked_add((l2_index as u64) * 8)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.write_at(offset, &entry.to_be_bytes())?;
+        self.backend_write_at(offset, &entry.to_be_bytes())?;
         let table = self
             .l2_cache
             .get_mut(&l2_offset)
@@ -776,8 +1004,7 @@ impl<B: StorageBackend> Qcow2Disk<B> {
             .l1_table_offset
             .checked_add((l1_index as u64) * 8)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend
-            .write_at(l1_entry_offset, &entry.to_be_bytes())?;
+        self.backend_write_at(l1_entry_offset, &entry.to_be_bytes())?;
         self.l1_table[l1_index] = entry;
 
         let l2_entries: usize = self
@@ -846,7 +1073,7 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         while remaining > 0 {
             let to_copy = remaining.min(buf.len() as u64) as usize;
             self.backend_read_at(src, &mut buf[..to_copy], "qcow2 data cluster truncated")?;
-            self.backend.write_at(dst, &buf[..to_copy])?;
+            self.backend_write_at(dst, &buf[..to_copy])?;
             src = src
                 .checked_add(to_copy as u64)
                 .ok_or(DiskError::OffsetOverflow)?;
@@ -865,7 +1092,9 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         let new_len = offset
             .checked_add(cluster_size)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.set_len(new_len)?;
+        self.backend
+            .set_len(new_len)
+            .map_err(|e| e.in_layer(DiskLayer::Qcow2))?;
         self.next_free_offset = new_len;
         Ok(offset)
     }
@@ -945,7 +1174,7 @@ impl<B: StorageBackend> Qcow2Disk<B> {
         let entry_offset = block_offset
             .checked_add((entry_index as u64) * 2)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.write_at(entry_offset, &value.to_be_bytes())?;
+        self.backend_write_at(entry_offset, &value.to_be_bytes())?;
         let block = self
             .refcount_cache
             .get_mut(&block_offset)
@@ -975,8 +1204,7 @@ impl<B: StorageBackend> Qcow2Disk<B> {
             .refcount_table_offset
             .checked_add((block_index as u64) * 8)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend
-            .write_at(entry_offset, &new_block_offset.to_be_bytes())?;
+        self.backend_write_at(entry_offset, &new_block_offset.to_be_bytes())?;
         self.refcount_table[block_index] = new_block_offset;
 
         self.ensure_refcount_block_cached(new_block_offset)?;
@@ -1051,15 +1279,15 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
             if aligned_full_cluster {
                 let max_clusters = (remaining / cluster_size_usize) as u64;
 
-                let first = self.lookup_data_cluster(guest_cluster_index)?;
+                let first = self.cluster_source(guest_cluster_index)?;
                 match first {
-                    Some(first_phys) => {
+                    ClusterSource::Data(first_phys) => {
                         let mut run_clusters = 1u64;
                         while run_clusters < max_clusters {
                             let idx = guest_cluster_index
                                 .checked_add(run_clusters)
                                 .ok_or(DiskError::OffsetOverflow)?;
-                            let Some(next_phys) = self.lookup_data_cluster(idx)? else {
+                            let ClusterSource::Data(next_phys) = self.cluster_source(idx)? else {
                                 break;
                             };
                             let expected = first_phys
@@ -1090,13 +1318,13 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                         pos += run_bytes;
                         continue;
                     }
-                    None => {
+                    source => {
                         let mut run_clusters = 1u64;
                         while run_clusters < max_clusters {
                             let idx = guest_cluster_index
                                 .checked_add(run_clusters)
                                 .ok_or(DiskError::OffsetOverflow)?;
-                            if self.lookup_data_cluster(idx)?.is_some() {
+                            if self.cluster_source(idx)? != source {
                                 break;
                             }
                             run_clusters += 1;
@@ -1109,10 +1337,10 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                             .try_into()
                             .map_err(|_| DiskError::OffsetOverflow)?;
 
-                        if let Some(backing) = self.backing.as_mut() {
-                            backing.read_at(cur_guest, &mut buf[pos..pos + run_bytes])?;
-                        } else {
+                        if source == ClusterSource::Zero {
                             buf[pos..pos + run_bytes].fill(0);
+                        } else {
+                            self.read_backing(cur_guest, &mut buf[pos..pos + run_bytes])?;
                         }
                         pos += run_bytes;
                         continue;
@@ -1121,19 +1349,21 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
             }
 
             // Slow path: partial-cluster read.
-            if let Some(data_cluster) = self.lookup_data_cluster(guest_cluster_index)? {
-                let phys = data_cluster
-                    .checked_add(offset_in_cluster as u64)
-                    .ok_or(DiskError::OffsetOverflow)?;
-                self.backend_read_at(
-                    phys,
-                    &mut buf[pos..pos + chunk_len],
-                    "qcow2 data cluster truncated",
-                )?;
-            } else if let Some(backing) = self.backing.as_mut() {
-                backing.read_at(cur_guest, &mut buf[pos..pos + chunk_len])?;
-            } else {
-                buf[pos..pos + chunk_len].fill(0);
+            match self.cluster_source(guest_cluster_index)? {
+                ClusterSource::Data(data_cluster) => {
+                    let phys = data_cluster
+                        .checked_add(offset_in_cluster as u64)
+                        .ok_or(DiskError::OffsetOverflow)?;
+                    self.backend_read_at(
+                        phys,
+                        &mut buf[pos..pos + chunk_len],
+                        "qcow2 data cluster truncated",
+                    )?;
+                }
+                ClusterSource::Zero => buf[pos..pos + chunk_len].fill(0),
+                ClusterSource::Backing => {
+                    self.read_backing(cur_guest, &mut buf[pos..pos + chunk_len])?;
+                }
             }
 
             pos += chunk_len;
@@ -1190,10 +1420,13 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                 None
             };
 
+            // Zero clusters already read as zeros and never expose backing contents.
+            let zero_cluster = existing.is_none() && (l2_entry & QCOW2_OFLAG_ZERO) != 0;
+
             // Preserve the existing optimization: zero writes to unallocated clusters don't
             // allocate new space. With a backing disk, we must still allocate to override the
             // parent contents (otherwise reads would continue to fall through).
-            if existing.is_none() && !has_backing && chunk_is_all_zero {
+            if existing.is_none() && chunk_is_all_zero && (!has_backing || zero_cluster) {
                 buf_off += chunk_len;
                 continue;
             }
@@ -1221,7 +1454,7 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                         let phys = old_off
                             .checked_add(offset_in_cluster as u64)
                             .ok_or(DiskError::OffsetOverflow)?;
-                        self.backend.write_at(phys, chunk)?;
+                        self.backend_write_at(phys, chunk)?;
                     } else {
                         // Shared cluster: copy-on-write.
                         let l2_offset = l2_offset_opt.ok_or(DiskError::CorruptImage(
@@ -1237,7 +1470,7 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                         let phys = new_off
                             .checked_add(offset_in_cluster as u64)
                             .ok_or(DiskError::OffsetOverflow)?;
-                        self.backend.write_at(phys, chunk)?;
+                        self.backend_write_at(phys, chunk)?;
 
                         // Ensure the new cluster is accounted for before linking it from the L2
                         // table (avoid refcount underflow if we crash mid-write).
@@ -1254,13 +1487,9 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                 None => {
                     // Unallocated cluster: allocate and write. If this is an overlay image, seed
                     // the newly-allocated cluster with backing contents for bytes we are not
-                    // overwriting.
+                    // overwriting (zero clusters keep their zeros).
                     let data_cluster = self.ensure_data_cluster(guest_cluster_index)?;
-                    if has_backing && !full_cluster_write {
-                        let Some(backing) = self.backing.as_mut() else {
-                            return Err(DiskError::CorruptImage("qcow2 backing disk missing"));
-                        };
-
+                    if has_backing && !zero_cluster && !full_cluster_write {
                         let mut scratch = [0u8; 4096];
 
                         // Prefix before the write.
@@ -1269,11 +1498,11 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                         let mut remaining_prefix = offset_in_cluster.min(max_len);
                         while remaining_prefix > 0 {
                             let n = remaining_prefix.min(scratch.len());
-                            backing.read_at(base_off, &mut scratch[..n])?;
+                            self.read_backing(base_off, &mut scratch[..n])?;
                             let phys = data_cluster
                                 .checked_add(child_off as u64)
                                 .ok_or(DiskError::OffsetOverflow)?;
-                            self.backend.write_at(phys, &scratch[..n])?;
+                            self.backend_write_at(phys, &scratch[..n])?;
                             base_off = base_off
                                 .checked_add(n as u64)
                                 .ok_or(DiskError::OffsetOverflow)?;
@@ -1291,11 +1520,11 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                             let mut remaining_suffix = max_len - write_end;
                             while remaining_suffix > 0 {
                                 let n = remaining_suffix.min(scratch.len());
-                                backing.read_at(base_off, &mut scratch[..n])?;
+                                self.read_backing(base_off, &mut scratch[..n])?;
                                 let phys = data_cluster
                                     .checked_add(child_off as u64)
                                     .ok_or(DiskError::OffsetOverflow)?;
-                                self.backend.write_at(phys, &scratch[..n])?;
+                                self.backend_write_at(phys, &scratch[..n])?;
                                 base_off = base_off
                                     .checked_add(n as u64)
                                     .ok_or(DiskError::OffsetOverflow)?;
@@ -1308,7 +1537,7 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
                     let phys = data_cluster
                         .checked_add(offset_in_cluster as u64)
                         .ok_or(DiskError::OffsetOverflow)?;
-                    self.backend.write_at(phys, chunk)?;
+                    self.backend_write_at(phys, chunk)?;
                 }
             }
 
@@ -1320,9 +1549,57 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for Qcow2Disk
 
     fn flush(&mut self) -> Result<()> {
         if let Some(backing) = self.backing.as_mut() {
-            backing.flush()?;
+            backing.flush().map_err(|e| e.in_layer(DiskLayer::Qcow2))?;
+        }
+        self.backend
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::Qcow2))
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        checked_range(
+            offset,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            self.capacity_bytes(),
+        )?;
+
+        let cluster_size = self.cluster_size();
+        if cluster_size == 0 {
+            return Err(DiskError::CorruptImage("qcow2 cluster size is zero"));
+        }
+        let end = offset + len;
+        let mut out = Vec::new();
+        let mut cur = offset;
+        while cur < end {
+            let status = self.cluster_status(cur / cluster_size)?;
+            let mut run_end = (cur / cluster_size + 1)
+                .saturating_mul(cluster_size)
+                .min(end);
+            while run_end < end && self.cluster_status(run_end / cluster_size)? == status {
+                run_end = (run_end / cluster_size + 1)
+                    .saturating_mul(cluster_size)
+                    .min(end);
+            }
+
+            // `read_at` passes unallocated clusters through to the backing disk, so report the
+            // backing disk's view of those (and `Unallocated` past its end).
+            match self.backing.as_mut() {
+                Some(backing) if status == AllocationStatus::Unallocated => {
+                    let backing_end = backing.capacity_bytes().clamp(cur, run_end);
+                    if backing_end > cur {
+                        for r in backing.allocation_status(cur, backing_end - cur)? {
+                            push_allocated_range(&mut out, r.offset, r.len, r.status);
+                        }
+                    }
+                    if backing_end < run_end {
+                        push_allocated_range(&mut out, backing_end, run_end - backing_end, status);
+                    }
+                }
+                _ => push_allocated_range(&mut out, cur, run_end - cur, status),
+            }
+            cur = run_end;
         }
-        self.backend.flush()
+        Ok(out)
     }
 }
 
@@ -1,4 +1,9 @@
-use serde::{Deserialize, Serialize};
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize, Serializer};
 
 /// A half-open byte range `[start, end)`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -21,17 +26,6 @@ impl ByteRange {
     pub fn is_empty(&self) -> bool {
         self.start >= self.end
     }
-
-    fn overlaps_or_adjacent(&self, other: &ByteRange) -> bool {
-        self.start <= other.end && other.start <= self.end
-    }
-
-    fn merge(&self, other: &ByteRange) -> ByteRange {
-        ByteRange {
-            start: self.start.min(other.start),
-            end: self.end.max(other.end),
-        }
-    }
 }
 
 /// A set of disjoint, sorted byte ranges.
@@ -39,128 +33,279 @@ impl ByteRange {
 /// Invariants:
 /// - Ranges are stored in ascending order.
 /// - No ranges overlap or touch (adjacent ranges are merged).
-#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Ranges live in a `BTreeMap` keyed by start offset, so insert, remove and point queries are
+/// `O(log n)` (plus the ranges merged or split) even with millions of entries. [`RangeSet::ranges`]
+/// returns a sorted snapshot that is rebuilt lazily after the set changes.
+///
+/// Serializes as `{"ranges": [{"start", "end"}, ...]}`. Deserializing normalizes the list: empty
+/// entries are dropped and overlapping or adjacent entries merged.
+#[derive(Default, Clone, Deserialize)]
+#[serde(from = "RangeSetRepr")]
 pub struct RangeSet {
+    /// `start -> end` of every range.
+    map: BTreeMap<u64, u64>,
+    total_len: u64,
+    /// Sorted snapshot backing [`RangeSet::ranges`]; cleared on every mutation.
+    snapshot: OnceLock<Vec<ByteRange>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RangeSetRepr {
     ranges: Vec<ByteRange>,
 }
 
+impl From<RangeSetRepr> for RangeSet {
+    fn from(repr: RangeSetRepr) -> Self {
+        repr.ranges.into_iter().collect()
+    }
+}
+
+impl Serialize for RangeSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            ranges: &'a [ByteRange],
+        }
+        Repr {
+            ranges: self.ranges(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl fmt::Debug for RangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeSet")
+            .field("ranges", &self.ranges())
+            .finish()
+    }
+}
+
+impl PartialEq for RangeSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl Eq for RangeSet {}
+
 impl RangeSet {
     pub fn new() -> Self {
-        Self { ranges: Vec::new() }
+        Self::default()
     }
 
+    /// Sorted snapshot of the ranges in the set.
+    ///
+    /// Building the snapshot is `O(n)` the first time it is requested after a change; prefer
+    /// [`RangeSet::iter`] on hot paths that interleave reads with inserts.
     pub fn ranges(&self) -> &[ByteRange] {
-        &self.ranges
+        self.snapshot.get_or_init(|| self.iter().collect())
+    }
+
+    /// Iterate the ranges in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = ByteRange> + '_ {
+        self.map
+            .iter()
+            .map(|(&start, &end)| ByteRange { start, end })
+    }
+
+    /// Number of disjoint ranges in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
     }
 
     pub fn total_len(&self) -> u64 {
-        self.ranges.iter().map(ByteRange::len).sum()
+        self.total_len
     }
 
     pub fn contains_range(&self, start: u64, end: u64) -> bool {
         if start >= end {
             return true;
         }
-        match self.ranges.binary_search_by(|r| {
-            if r.end <= start {
-                std::cmp::Ordering::Less
-            } else {
-                std::cmp::Ordering::Greater
-            }
-        }) {
-            Ok(idx) | Err(idx) => {
-                if idx >= self.ranges.len() {
-                    return false;
-                }
-                let r = self.ranges[idx];
-                r.start <= start && r.end >= end
-            }
-        }
+        self.map
+            .range(..=start)
+            .next_back()
+            .is_some_and(|(_, &r_end)| r_end >= end)
     }
 
     /// Insert the given range, merging overlaps/adjacent ranges.
     pub fn insert(&mut self, start: u64, end: u64) {
-        let mut new = ByteRange::new(start, end);
-        if new.is_empty() {
+        if start >= end {
             return;
         }
+        self.snapshot.take();
 
-        let mut out = Vec::with_capacity(self.ranges.len() + 1);
-        let mut inserted = false;
-
-        for r in self.ranges.drain(..) {
-            if r.end < new.start {
-                out.push(r);
-                continue;
+        let (mut start, mut end) = (start, end);
+        if let Some((&prev_start, &prev_end)) = self.map.range(..start).next_back() {
+            if prev_end >= start {
+                start = prev_start;
+                end = end.max(prev_end);
             }
-            if new.end < r.start {
-                if !inserted {
-                    out.push(new);
-                    inserted = true;
+        }
+        // Everything starting inside (or right at the end of) the new range is absorbed.
+        while let Some((&r_start, &r_end)) = self.map.range(start..=end).next() {
+            self.map.remove(&r_start);
+            self.total_len -= r_end - r_start;
+            end = end.max(r_end);
+        }
+        self.map.insert(start, end);
+        self.total_len += end - start;
+    }
+
+    /// Insert every range, merging overlaps/adjacent ranges.
+    ///
+    /// The batch is sorted and coalesced first, so each resulting run costs a single `O(log n)`
+    /// insert no matter how fragmented the input is.
+    pub fn insert_many<I: IntoIterator<Item = ByteRange>>(&mut self, ranges: I) {
+        let mut batch: Vec<ByteRange> = ranges.into_iter().filter(|r| !r.is_empty()).collect();
+        batch.sort_unstable_by_key(|r| r.start);
+
+        let mut pending: Option<ByteRange> = None;
+        for r in batch {
+            match &mut pending {
+                Some(cur) if r.start <= cur.end => cur.end = cur.end.max(r.end),
+                _ => {
+                    if let Some(cur) = pending.replace(r) {
+                        self.insert(cur.start, cur.end);
+                    }
                 }
-                out.push(r);
-                continue;
             }
-            // Overlapping or adjacent.
-            new = new.merge(&r);
         }
-
-        if !inserted {
-            out.push(new);
+        if let Some(cur) = pending {
+            self.insert(cur.start, cur.end);
         }
-
-        self.ranges = out;
-        self.compact_adjacent();
     }
 
     /// Remove the given range from the set.
     pub fn remove(&mut self, start: u64, end: u64) {
-        let remove = ByteRange::new(start, end);
-        if remove.is_empty() {
+        if start >= end {
             return;
         }
+        self.snapshot.take();
 
-        let mut out = Vec::with_capacity(self.ranges.len());
-        for r in self.ranges.drain(..) {
-            // No overlap
-            if r.end <= remove.start || r.start >= remove.end {
-                out.push(r);
-                continue;
+        let affected: Vec<(u64, u64)> = self.overlapping(start, end).collect();
+        for (r_start, r_end) in affected {
+            self.map.remove(&r_start);
+            self.total_len -= r_end - r_start;
+            // Left and right remainders.
+            if r_start < start {
+                self.map.insert(r_start, start);
+                self.total_len += start - r_start;
             }
-
-            // Left remainder
-            if r.start < remove.start {
-                out.push(ByteRange::new(r.start, remove.start));
+            if r_end > end {
+                self.map.insert(end, r_end);
+                self.total_len += r_end - end;
             }
+        }
+    }
+
+    /// Keep only the bytes that are also in `other`.
+    pub fn intersect_with(&mut self, other: &RangeSet) {
+        let (small, large) = if self.len() <= other.len() {
+            (&*self, other)
+        } else {
+            (other, &*self)
+        };
 
-            // Right remainder
-            if r.end > remove.end {
-                out.push(ByteRange::new(remove.end, r.end));
+        // Pieces cut from disjoint, non-adjacent inputs are themselves disjoint and non-adjacent,
+        // so they can be stored as-is.
+        let mut out = RangeSet::new();
+        for r in small.iter() {
+            for (o_start, o_end) in large.overlapping(r.start, r.end) {
+                let (start, end) = (o_start.max(r.start), o_end.min(r.end));
+                out.map.insert(start, end);
+                out.total_len += end - start;
             }
         }
-        self.ranges = out;
-        self.compact_adjacent();
+        *self = out;
     }
 
-    fn compact_adjacent(&mut self) {
-        if self.ranges.len() <= 1 {
-            return;
+    /// The bytes of `bounds` that are *not* in the set.
+    pub fn invert_within(&self, bounds: ByteRange) -> RangeSet {
+        let mut out = RangeSet::new();
+        for gap in self.gaps(bounds.start, bounds.end) {
+            out.map.insert(gap.start, gap.end);
+            out.total_len += gap.len();
+        }
+        out
+    }
+
+    /// Iterate, in ascending order, the sub-ranges of `[start, end)` that are not in the set.
+    ///
+    /// This is what a read of `[start, end)` still has to fetch.
+    pub fn gaps(&self, start: u64, end: u64) -> Gaps<'_> {
+        let mut cursor = start;
+        if let Some((_, &prev_end)) = self.map.range(..start).next_back() {
+            cursor = cursor.max(prev_end);
+        }
+        Gaps {
+            ranges: self.map.range(start..end.max(start)),
+            cursor,
+            end,
         }
+    }
 
-        self.ranges.sort_by_key(|r| r.start);
+    /// `(start, end)` of every range that shares at least one byte with `[start, end)`.
+    fn overlapping(&self, start: u64, end: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let prev = self
+            .map
+            .range(..start)
+            .next_back()
+            .filter(|(_, &r_end)| r_end > start);
+        prev.into_iter()
+            .chain(self.map.range(start..end.max(start)))
+            .map(|(&r_start, &r_end)| (r_start, r_end))
+    }
+}
+
+impl FromIterator<ByteRange> for RangeSet {
+    fn from_iter<I: IntoIterator<Item = ByteRange>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        set.insert_many(iter);
+        set
+    }
+}
 
-        let mut compacted = Vec::with_capacity(self.ranges.len());
-        let mut cur = self.ranges[0];
-        for r in self.ranges.iter().skip(1).copied() {
-            if cur.overlaps_or_adjacent(&r) {
-                cur = cur.merge(&r);
-            } else {
-                compacted.push(cur);
-                cur = r;
+impl Extend<ByteRange> for RangeSet {
+    fn extend<I: IntoIterator<Item = ByteRange>>(&mut self, iter: I) {
+        self.insert_many(iter);
+    }
+}
+
+/// Iterator over the uncovered parts of a bound; see [`RangeSet::gaps`].
+pub struct Gaps<'a> {
+    ranges: btree_map::Range<'a, u64, u64>,
+    cursor: u64,
+    end: u64,
+}
+
+impl Iterator for Gaps<'_> {
+    type Item = ByteRange;
+
+    fn next(&mut self) -> Option<ByteRange> {
+        while self.cursor < self.end {
+            let gap = match self.ranges.next() {
+                Some((&r_start, &r_end)) => {
+                    let gap = ByteRange::new(self.cursor, r_start);
+                    self.cursor = r_end;
+                    gap
+                }
+                None => {
+                    let gap = ByteRange::new(self.cursor, self.end);
+                    self.cursor = self.end;
+                    gap
+                }
+            };
+            if !gap.is_empty() {
+                return Some(gap);
             }
         }
-        compacted.push(cur);
-        self.ranges = compacted;
+        None
     }
 }
 
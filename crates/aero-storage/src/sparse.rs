@@ -1,9 +1,22 @@
-use crate::util::{align_up_u64, checked_range, div_ceil_u64};
-use crate::{DiskError, Result, StorageBackend, VirtualDisk, SECTOR_SIZE};
+use crate::disk::push_allocated_range;
+use crate::util::{
+    align_up_u64, check_grow, checked_range, crc32c, crc32c_raw, crc32c_raw_zeros, div_ceil_u64,
+};
+use crate::{
+    AllocatedRange, AllocationStatus, DiskError, DiskLayer, Result, StorageBackend, VirtualDisk,
+    SECTOR_SIZE,
+};
 
 const MAGIC: &[u8; 8] = b"AEROSPAR";
 const VERSION: u32 = 1;
+/// Version 2 turns the reserved header word into `flags`. Images without flags are still written
+/// as version 1 so older readers keep opening them.
+const VERSION_FLAGS: u32 = 2;
 pub const HEADER_SIZE: usize = 64;
+/// Header flag: a block checksum table follows the allocation table.
+const FLAG_BLOCK_CHECKSUMS: u32 = 1 << 0;
+/// Set in a checksum table entry whose low 32 bits hold the block's CRC32C.
+const CHECKSUM_VALID: u64 = 1 << 32;
 const ZERO_BUF: [u8; 4096] = [0; 4096];
 
 // Hard cap to avoid absurd allocations from untrusted images.
@@ -25,11 +38,56 @@ pub struct AeroSparseConfig {
     /// Larger blocks reduce metadata size and improve sequential throughput, but increase
     /// write amplification for small random writes. 1 MiB is a good starting point.
     pub block_size_bytes: u32,
+    /// Keep a CRC32C of every allocated block (format version 2), checked when a block is first
+    /// read after opening and by [`AeroSparseDisk::scrub`].
+    ///
+    /// Every partial write additionally reads back the bytes it replaces and updates the checksum
+    /// entry twice, and new blocks are zero-filled in full.
+    pub block_checksums: bool,
+}
+
+impl Default for AeroSparseConfig {
+    /// An empty disk with 1 MiB blocks and no block checksums; set `disk_size_bytes` before
+    /// creating a disk.
+    fn default() -> Self {
+        Self {
+            disk_size_bytes: 0,
+            block_size_bytes: 1024 * 1024,
+            block_checksums: false,
+        }
+    }
+}
+
+/// What reads do when a block does not match its checksum.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ChecksumMismatchPolicy {
+    /// Fail the read with [`DiskError::ChecksumMismatch`].
+    #[default]
+    Error,
+    /// Log a warning, count the mismatch (see [`AeroSparseDisk::checksum_mismatches`]) and return
+    /// the data as stored.
+    LogAndContinue,
+}
+
+/// Result of [`AeroSparseDisk::scrub`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScrubReport {
+    /// Allocated blocks read by the scrub.
+    pub blocks_checked: u64,
+    /// Allocated blocks that had no checksum recorded (a crash interrupted a write to them); the
+    /// checksum of their current contents was recorded.
+    pub checksums_recorded: u64,
+    /// Logical indices of blocks whose contents do not match their checksum.
+    pub corrupt_blocks: Vec<u64>,
+    /// `false` if the progress callback stopped the scrub early.
+    pub completed: bool,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct AeroSparseHeader {
     pub version: u32,
+    /// Feature flags (version 2; always 0 in version 1 images).
+    pub flags: u32,
     pub block_size_bytes: u32,
     pub disk_size_bytes: u64,
     pub table_entries: u64,
@@ -44,7 +102,7 @@ impl AeroSparseHeader {
         out[8..12].copy_from_slice(&self.version.to_le_bytes());
         out[12..16].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
         out[16..20].copy_from_slice(&self.block_size_bytes.to_le_bytes());
-        out[20..24].copy_from_slice(&0u32.to_le_bytes()); // reserved
+        out[20..24].copy_from_slice(&self.flags.to_le_bytes()); // reserved in version 1
         out[24..32].copy_from_slice(&self.disk_size_bytes.to_le_bytes());
         out[32..40].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes()); // table_offset
         out[40..48].copy_from_slice(&self.table_entries.to_le_bytes());
@@ -67,7 +125,7 @@ impl AeroSparseHeader {
                 .try_into()
                 .map_err(|_| DiskError::InvalidSparseHeader("header too small"))?,
         );
-        if version != VERSION {
+        if version != VERSION && version != VERSION_FLAGS {
             return Err(DiskError::InvalidSparseHeader("unsupported version"));
         }
         let header_size = u32::from_le_bytes(
@@ -87,6 +145,20 @@ impl AeroSparseHeader {
                 .try_into()
                 .map_err(|_| DiskError::InvalidSparseHeader("header too small"))?,
         );
+        let flags = if version >= VERSION_FLAGS {
+            u32::from_le_bytes(
+                bytes
+                    .get(20..24)
+                    .ok_or(DiskError::InvalidSparseHeader("header too small"))?
+                    .try_into()
+                    .map_err(|_| DiskError::InvalidSparseHeader("header too small"))?,
+            )
+        } else {
+            0
+        };
+        if flags & !FLAG_BLOCK_CHECKSUMS != 0 {
+            return Err(DiskError::Unsupported("unknown aerosparse header flags"));
+        }
         let disk_size_bytes = u64::from_le_bytes(
             bytes
                 .get(24..32)
@@ -170,10 +242,8 @@ impl AeroSparseHeader {
             ));
         }
 
-        let table_end = (HEADER_SIZE as u64)
-            .checked_add(expected_table_bytes)
-            .ok_or(DiskError::OffsetOverflow)?;
-        let expected_data_offset = align_up_u64(table_end, block_size)?;
+        let expected_data_offset =
+            data_offset_for(table_entries, block_size, flags & FLAG_BLOCK_CHECKSUMS != 0)?;
         if expected_data_offset != data_offset {
             return Err(DiskError::InvalidSparseHeader("unexpected data_offset"));
         }
@@ -186,6 +256,7 @@ impl AeroSparseHeader {
 
         Ok(Self {
             version,
+            flags,
             block_size_bytes,
             disk_size_bytes,
             table_entries,
@@ -197,6 +268,120 @@ impl AeroSparseHeader {
     pub fn block_size_u64(&self) -> u64 {
         self.block_size_bytes as u64
     }
+
+    /// Whether the image stores a CRC32C per allocated block.
+    pub fn has_block_checksums(&self) -> bool {
+        self.flags & FLAG_BLOCK_CHECKSUMS != 0
+    }
+
+    /// Byte offset of the checksum table, directly after the allocation table.
+    fn checksum_table_offset(&self) -> Result<u64> {
+        self.table_entries
+            .checked_mul(8)
+            .and_then(|bytes| bytes.checked_add(HEADER_SIZE as u64))
+            .ok_or(DiskError::OffsetOverflow)
+    }
+}
+
+/// Start of the data region: the end of the allocation table (and checksum table, if present)
+/// rounded up to a whole block.
+fn backend_read_at<B: StorageBackend>(backend: &mut B, offset: u64, buf: &mut [u8]) -> Result<()> {
+    let len = buf.len();
+    backend
+        .read_at(offset, buf)
+        .map_err(|e| e.with_context(DiskLayer::AeroSparse, offset, len))
+}
+
+fn backend_write_at<B: StorageBackend>(backend: &mut B, offset: u64, buf: &[u8]) -> Result<()> {
+    backend
+        .write_at(offset, buf)
+        .map_err(|e| e.with_context(DiskLayer::AeroSparse, offset, buf.len()))
+}
+
+fn backend_len<B: StorageBackend>(backend: &mut B) -> Result<u64> {
+    backend.len().map_err(|e| e.in_layer(DiskLayer::AeroSparse))
+}
+
+fn backend_set_len<B: StorageBackend>(backend: &mut B, len: u64) -> Result<()> {
+    backend
+        .set_len(len)
+        .map_err(|e| e.in_layer(DiskLayer::AeroSparse))
+}
+
+fn data_offset_for(table_entries: u64, block_size: u64, checksums: bool) -> Result<u64> {
+    let tables = if checksums { 2 } else { 1 };
+    let tables_end = table_entries
+        .checked_mul(8 * tables)
+        .and_then(|bytes| bytes.checked_add(HEADER_SIZE as u64))
+        .ok_or(DiskError::OffsetOverflow)?;
+    align_up_u64(tables_end, block_size)
+}
+
+/// CRC32C of a block of zeros.
+fn zero_block_crc(block_size: u64) -> u32 {
+    !crc32c_raw_zeros(!0, block_size)
+}
+
+/// Bytes written into a block by [`AeroSparseDisk::write_block_range`].
+#[derive(Copy, Clone)]
+enum BlockData<'a> {
+    Bytes(&'a [u8]),
+    Zeros(usize),
+}
+
+impl BlockData<'_> {
+    fn len(&self) -> usize {
+        match self {
+            BlockData::Bytes(src) => src.len(),
+            BlockData::Zeros(len) => *len,
+        }
+    }
+}
+
+/// In-memory copy of the checksum table plus per-session verification state.
+struct BlockChecksums {
+    /// Checksum table entries, indexed by logical block: `CHECKSUM_VALID | crc32c`, or 0 when no
+    /// checksum is recorded.
+    entries: Vec<u64>,
+    /// Bitset of logical blocks checked against their checksum since the image was opened. Each
+    /// block is verified on its first read rather than on every read, since that requires reading
+    /// the whole block.
+    verified: Vec<u64>,
+    policy: ChecksumMismatchPolicy,
+    mismatches: u64,
+}
+
+impl BlockChecksums {
+    fn new(entries: Vec<u64>) -> Self {
+        let verified = vec![0; entries.len().div_ceil(64)];
+        Self {
+            entries,
+            verified,
+            policy: ChecksumMismatchPolicy::default(),
+            mismatches: 0,
+        }
+    }
+
+    fn crc(&self, block_idx: usize) -> Option<u32> {
+        let entry = *self.entries.get(block_idx)?;
+        (entry & CHECKSUM_VALID != 0).then_some(entry as u32)
+    }
+
+    fn is_verified(&self, block_idx: usize) -> bool {
+        self.verified
+            .get(block_idx / 64)
+            .is_some_and(|word| word & (1 << (block_idx % 64)) != 0)
+    }
+
+    fn set_verified(&mut self, block_idx: usize, verified: bool) {
+        if let Some(word) = self.verified.get_mut(block_idx / 64) {
+            if verified {
+                *word |= 1 << (block_idx % 64);
+            } else {
+                *word &= !(1 << (block_idx % 64));
+            }
+        }
+    }
 }
 
 /// Aero-specific sparse disk format.
@@ -205,6 +390,9 @@ impl AeroSparseHeader {
 /// - Header (64 bytes)
 /// - Allocation table (`table_entries` u64s). Each entry stores the physical byte offset
 ///   of the data block, or 0 if unallocated.
+/// - Checksum table (only with [`AeroSparseConfig::block_checksums`]; `table_entries` u64s).
+///   Each entry stores `1 << 32 | crc32c` of the whole logical block, or 0 if no checksum is
+///   recorded.
 /// - Data area: fixed-size blocks appended as they are allocated.
 pub struct AeroSparseDisk<B> {
     backend: B,
@@ -218,6 +406,7 @@ pub struct AeroSparseDisk<B> {
     /// entries may be smaller due to deallocations.
     phys_used: Vec<u64>,
     mapped_blocks: u64,
+    checksums: Option<BlockChecksums>,
 }
 
 impl<B: StorageBackend> AeroSparseDisk<B> {
@@ -254,13 +443,16 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
                 "aerosparse allocation table too large",
             ));
         }
-        let table_end = (HEADER_SIZE as u64)
-            .checked_add(table_bytes)
-            .ok_or(DiskError::OffsetOverflow)?;
-        let data_offset = align_up_u64(table_end, block_size)?;
+        let data_offset = data_offset_for(table_entries, block_size, cfg.block_checksums)?;
 
+        let (version, flags) = if cfg.block_checksums {
+            (VERSION_FLAGS, FLAG_BLOCK_CHECKSUMS)
+        } else {
+            (VERSION, 0)
+        };
         let header = AeroSparseHeader {
-            version: VERSION,
+            version,
+            flags,
             block_size_bytes: cfg.block_size_bytes,
             disk_size_bytes: cfg.disk_size_bytes,
             table_entries,
@@ -268,7 +460,8 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
             allocated_blocks: 0,
         };
 
-        // Ensure the table region exists (filled with zeros).
+        // Ensure the table region exists (filled with zeros). A zeroed checksum table records no
+        // checksums.
         backend.set_len(data_offset)?;
         backend.write_at(0, &header.encode())?;
 
@@ -278,12 +471,17 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
             .map_err(|_| DiskError::InvalidConfig("aerosparse allocation table too large"))?;
         table.resize(table_entries_usize, 0);
 
+        let checksums = cfg
+            .block_checksums
+            .then(|| BlockChecksums::new(vec![0; table_entries_usize]));
+
         Ok(Self {
             backend,
             header,
             table,
             phys_used: Vec::new(),
             mapped_blocks: 0,
+            checksums,
         })
     }
 
@@ -327,6 +525,16 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
                 "allocation table out of bounds",
             ));
         }
+        if header.has_block_checksums()
+            && backend_len
+                < table_end
+                    .checked_add(expected_table_bytes)
+                    .ok_or(DiskError::OffsetOverflow)?
+        {
+            return Err(DiskError::CorruptSparseImage(
+                "checksum table out of bounds",
+            ));
+        }
         if backend_len < header.data_offset {
             return Err(DiskError::CorruptSparseImage("data region out of bounds"));
         }
@@ -461,12 +669,52 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
             remaining -= read_len;
         }
 
+        let checksums = if header.has_block_checksums() {
+            let mut entries: Vec<u64> = Vec::new();
+            entries
+                .try_reserve_exact(table_entries_usize)
+                .map_err(|_| DiskError::Unsupported("aerosparse allocation table too large"))?;
+            let mut offset = table_end;
+            let mut remaining = expected_table_bytes_usize;
+            while remaining > 0 {
+                let read_len = remaining.min(buf.len());
+                backend
+                    .read_at(offset, &mut buf[..read_len])
+                    .map_err(|e| match e {
+                        DiskError::OutOfBounds { .. } => {
+                            DiskError::CorruptSparseImage("checksum table out of bounds")
+                        }
+                        other => other,
+                    })?;
+                for chunk in buf[..read_len].chunks_exact(8) {
+                    let bytes: [u8; 8] = chunk
+                        .try_into()
+                        .map_err(|_| DiskError::CorruptSparseImage("checksum table chunk size"))?;
+                    let entry = u64::from_le_bytes(bytes);
+                    if entry & !(CHECKSUM_VALID | u64::from(u32::MAX)) != 0 {
+                        return Err(DiskError::CorruptSparseImage(
+                            "invalid block checksum entry",
+                        ));
+                    }
+                    entries.push(entry);
+                }
+                offset = offset
+                    .checked_add(read_len as u64)
+                    .ok_or(DiskError::OffsetOverflow)?;
+                remaining -= read_len;
+            }
+            Some(BlockChecksums::new(entries))
+        } else {
+            None
+        };
+
         Ok(Self {
             backend,
             header,
             table,
             phys_used: seen_phys_idx,
             mapped_blocks,
+            checksums,
         })
     }
 
@@ -494,6 +742,67 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
         self.mapped_blocks
     }
 
+    /// Choose how reads react to a block that does not match its checksum. Has no effect on
+    /// images without block checksums.
+    pub fn set_checksum_mismatch_policy(&mut self, policy: ChecksumMismatchPolicy) {
+        if let Some(sums) = self.checksums.as_mut() {
+            sums.policy = policy;
+        }
+    }
+
+    /// Number of checksum mismatches reads let through under
+    /// [`ChecksumMismatchPolicy::LogAndContinue`] (each block is counted once per session).
+    pub fn checksum_mismatches(&self) -> u64 {
+        self.checksums.as_ref().map_or(0, |sums| sums.mismatches)
+    }
+
+    /// Check every allocated block against its checksum.
+    ///
+    /// Blocks are read in 64 KiB pieces and `progress(done, total)` is called after each of the
+    /// `total` allocated blocks, so a long scrub can report progress or yield between blocks;
+    /// returning `false` stops it early with `completed: false`. Allocated blocks without a
+    /// recorded checksum get the checksum of their current contents. Mismatches are reported
+    /// rather than returned as errors, whatever the [`ChecksumMismatchPolicy`].
+    ///
+    /// Fails with [`DiskError::Unsupported`] for images created without block checksums.
+    pub fn scrub(&mut self, mut progress: impl FnMut(u64, u64) -> bool) -> Result<ScrubReport> {
+        if self.checksums.is_none() {
+            return Err(DiskError::Unsupported(
+                "aerosparse image has no block checksums",
+            ));
+        }
+
+        let total = self.mapped_blocks;
+        let mut report = ScrubReport {
+            completed: true,
+            ..ScrubReport::default()
+        };
+        for block_idx in 0..self.table.len() {
+            let phys = self.table[block_idx];
+            if phys == 0 {
+                continue;
+            }
+            let actual = self.block_crc(phys)?;
+            match self.checksums.as_ref().and_then(|sums| sums.crc(block_idx)) {
+                Some(expected) if expected != actual => {
+                    report.corrupt_blocks.push(block_idx as u64)
+                }
+                Some(_) => self.set_verified(block_idx, true),
+                None => {
+                    self.set_checksum(block_idx as u64, Some(actual))?;
+                    self.set_verified(block_idx, true);
+                    report.checksums_recorded += 1;
+                }
+            }
+            report.blocks_checked += 1;
+            if !progress(report.blocks_checked, total) {
+                report.completed = false;
+                break;
+            }
+        }
+        Ok(report)
+    }
+
     fn phys_offset_for_idx(&self, phys_idx: u64) -> Result<u64> {
         let block_size = self.header.block_size_u64();
         self.header
@@ -594,7 +903,7 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
 
         // Persist the updated header before truncating the backend, to ensure the image is
         // never left in a state where it references data beyond the backend length.
-        self.backend.write_at(0, &self.header.encode())?;
+        backend_write_at(&mut self.backend, 0, &self.header.encode())?;
 
         let block_size = self.header.block_size_u64();
         let new_end = self
@@ -606,9 +915,9 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
                     .ok_or(DiskError::OffsetOverflow)?,
             )
             .ok_or(DiskError::OffsetOverflow)?;
-        let cur_len = self.backend.len()?;
+        let cur_len = backend_len(&mut self.backend)?;
         if new_end < cur_len {
-            self.backend.set_len(new_end)?;
+            backend_set_len(&mut self.backend, new_end)?;
         }
         Ok(())
     }
@@ -671,22 +980,36 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
             .checked_add(1)
             .ok_or(DiskError::OffsetOverflow)?;
 
+        // A checksum left behind by an earlier mapping of this logical block (e.g. a crash during
+        // discard) must not apply to the new one.
+        self.set_checksum(block_idx, None)?;
+
         // Persist the updated header (if changed) and the single updated table entry immediately.
         if new_phys_slot {
-            self.backend.write_at(0, &self.header.encode())?;
+            backend_write_at(&mut self.backend, 0, &self.header.encode())?;
         }
         let table_entry_off = (HEADER_SIZE as u64)
             .checked_add(block_idx.checked_mul(8).ok_or(DiskError::OffsetOverflow)?)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend
-            .write_at(table_entry_off, &phys.to_le_bytes())?;
+        backend_write_at(&mut self.backend, table_entry_off, &phys.to_le_bytes())?;
 
         // Ensure the file covers the newly allocated block (some backends rely on set_len).
         let end = phys
             .checked_add(block_size)
             .ok_or(DiskError::OffsetOverflow)?;
-        if end > self.backend.len()? {
-            self.backend.set_len(end)?;
+        if end > backend_len(&mut self.backend)? {
+            backend_set_len(&mut self.backend, end)?;
+        }
+
+        // With block checksums, new blocks start out zeroed with a known checksum so that writes
+        // into them can update it incrementally.
+        if self.checksums.is_some() {
+            let block_size_usize: usize = block_size
+                .try_into()
+                .map_err(|_| DiskError::OffsetOverflow)?;
+            self.write_raw(phys, 0, BlockData::Zeros(block_size_usize))?;
+            self.set_checksum(block_idx, Some(zero_block_crc(block_size)))?;
+            self.set_verified(block_idx_usize, true);
         }
 
         Ok((phys, false))
@@ -715,8 +1038,9 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
         let table_entry_off = (HEADER_SIZE as u64)
             .checked_add(block_idx.checked_mul(8).ok_or(DiskError::OffsetOverflow)?)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend
-            .write_at(table_entry_off, &0u64.to_le_bytes())?;
+        backend_write_at(&mut self.backend, table_entry_off, &0u64.to_le_bytes())?;
+        self.set_checksum(block_idx, None)?;
+        self.set_verified(block_idx_usize, false);
 
         // Update the in-memory phys-used bitmap.
         let block_size = self.header.block_size_u64();
@@ -802,6 +1126,188 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
         self.trim_trailing_free_phys()
     }
 
+    /// Grow the virtual disk to `new_capacity` bytes.
+    ///
+    /// The allocation table lives directly in front of the data region, so a larger table can
+    /// push `data_offset` forward by whole blocks. Any data blocks occupying that space are first
+    /// copied to fresh slots at the end of the image and re-pointed, then the header is switched
+    /// to the new layout in a single write. Until that final header write the image remains a
+    /// valid instance of the old layout, except when evacuation needs more physical slots than
+    /// the old table has entries (a nearly fully allocated image).
+    ///
+    /// With block checksums the checksum table moves along with the allocation table. The old one
+    /// is cleared before the header switch and the new one is written after it, so an interrupted
+    /// resize leaves blocks without checksums (see [`Self::scrub`]) but never with misplaced ones.
+    pub fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        if !check_grow(self.header.disk_size_bytes, new_capacity)? {
+            return Ok(());
+        }
+
+        let block_size = self.header.block_size_u64();
+        let old_capacity = self.header.disk_size_bytes;
+        let old_entries = self.header.table_entries;
+        let old_data_offset = self.header.data_offset;
+
+        let new_entries = div_ceil_u64(new_capacity, block_size)?;
+        let new_table_bytes = new_entries
+            .checked_mul(8)
+            .ok_or(DiskError::OffsetOverflow)?;
+        if new_table_bytes > MAX_TABLE_BYTES || new_entries > MAX_TABLE_ENTRIES {
+            return Err(DiskError::InvalidConfig(
+                "aerosparse allocation table too large",
+            ));
+        }
+        let new_entries_usize: usize = new_entries
+            .try_into()
+            .map_err(|_| DiskError::InvalidConfig("aerosparse allocation table too large"))?;
+        let new_table_end = (HEADER_SIZE as u64)
+            .checked_add(new_table_bytes)
+            .ok_or(DiskError::OffsetOverflow)?;
+        let checksums = self.header.has_block_checksums();
+        let new_tables_end = if checksums {
+            new_table_end
+                .checked_add(new_table_bytes)
+                .ok_or(DiskError::OffsetOverflow)?
+        } else {
+            new_table_end
+        };
+        let new_data_offset = data_offset_for(new_entries, block_size, checksums)?;
+        let shift_blocks = (new_data_offset - old_data_offset) / block_size;
+
+        self.table
+            .try_reserve_exact(new_entries_usize.saturating_sub(self.table.len()))
+            .map_err(|_| DiskError::InvalidConfig("aerosparse allocation table too large"))?;
+
+        // Bytes past the old capacity in a partially used final block must read back as zeros.
+        let tail = old_capacity % block_size;
+        if tail != 0 {
+            let last = *self
+                .table
+                .last()
+                .ok_or(DiskError::CorruptSparseImage("block index out of range"))?;
+            if last != 0 {
+                let tail = tail as usize;
+                let last_idx = self.table.len() as u64 - 1;
+                self.write_zeros_in_block(last_idx, last, tail, block_size as usize - tail)?;
+            }
+        }
+
+        // Physical slots below `shift_blocks` overlap the grown table and must be evacuated.
+        let mut moves: Vec<(usize, u64)> = Vec::new();
+        if shift_blocks > 0 {
+            for (idx, &phys) in self.table.iter().enumerate() {
+                if phys != 0 && (phys - old_data_offset) / block_size < shift_blocks {
+                    moves.push((idx, phys));
+                }
+            }
+        }
+        let first_free_slot = self.header.allocated_blocks.max(shift_blocks);
+        let total_slots = first_free_slot
+            .checked_add(moves.len() as u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+
+        if !moves.is_empty() {
+            let end = old_data_offset
+                .checked_add(
+                    total_slots
+                        .checked_mul(block_size)
+                        .ok_or(DiskError::OffsetOverflow)?,
+                )
+                .ok_or(DiskError::OffsetOverflow)?;
+            if end > backend_len(&mut self.backend)? {
+                backend_set_len(&mut self.backend, end)?;
+            }
+
+            let mut buf = vec![0u8; (block_size as usize).min(64 * 1024)];
+            for (slot, &(_, src)) in (first_free_slot..).zip(moves.iter()) {
+                let dst = self.phys_offset_for_idx(slot)?;
+                let mut off = 0u64;
+                while off < block_size {
+                    let len = (block_size - off).min(buf.len() as u64) as usize;
+                    backend_read_at(&mut self.backend, src + off, &mut buf[..len])?;
+                    backend_write_at(&mut self.backend, dst + off, &buf[..len])?;
+                    off += len as u64;
+                }
+            }
+
+            // Publish the new slots before any table entry references them.
+            let interim = AeroSparseHeader {
+                allocated_blocks: total_slots,
+                ..self.header
+            };
+            backend_write_at(&mut self.backend, 0, &interim.encode())?;
+
+            for (slot, &(idx, _)) in (first_free_slot..).zip(moves.iter()) {
+                let dst = self.phys_offset_for_idx(slot)?;
+                let entry_off = (HEADER_SIZE as u64)
+                    .checked_add((idx as u64) * 8)
+                    .ok_or(DiskError::OffsetOverflow)?;
+                backend_write_at(&mut self.backend, entry_off, &dst.to_le_bytes())?;
+                self.table[idx] = dst;
+            }
+        }
+
+        // The new table entries must start out unallocated. This also clears the old checksum
+        // table, whose entries the new layout would otherwise read at shifted offsets.
+        let old_table_end = (HEADER_SIZE as u64) + old_entries * 8;
+        if new_data_offset > backend_len(&mut self.backend)? {
+            backend_set_len(&mut self.backend, new_data_offset)?;
+        }
+        let mut off = old_table_end;
+        while off < new_tables_end {
+            let len = (new_tables_end - off).min(ZERO_BUF.len() as u64) as usize;
+            backend_write_at(&mut self.backend, off, &ZERO_BUF[..len])?;
+            off += len as u64;
+        }
+
+        let allocated_blocks = if moves.is_empty() {
+            self.header.allocated_blocks.saturating_sub(shift_blocks)
+        } else {
+            total_slots - shift_blocks
+        };
+        let header = AeroSparseHeader {
+            disk_size_bytes: new_capacity,
+            table_entries: new_entries,
+            data_offset: new_data_offset,
+            allocated_blocks,
+            ..self.header
+        };
+        backend_write_at(&mut self.backend, 0, &header.encode())?;
+        self.header = header;
+        self.table.resize(new_entries_usize, 0);
+
+        if let Some(sums) = self.checksums.as_mut() {
+            sums.entries.resize(new_entries_usize, 0);
+            sums.verified.resize(new_entries_usize.div_ceil(64), 0);
+            let mut bytes = Vec::with_capacity(sums.entries.len().min(8 * 1024) * 8);
+            let mut off = self.header.checksum_table_offset()?;
+            for chunk in sums.entries.chunks(8 * 1024) {
+                bytes.clear();
+                for entry in chunk {
+                    bytes.extend_from_slice(&entry.to_le_bytes());
+                }
+                backend_write_at(&mut self.backend, off, &bytes)?;
+                off += bytes.len() as u64;
+            }
+        }
+
+        if shift_blocks > 0 {
+            let words: usize = allocated_blocks
+                .div_ceil(64)
+                .try_into()
+                .map_err(|_| DiskError::OffsetOverflow)?;
+            self.phys_used = vec![0; words];
+            for i in 0..self.table.len() {
+                let phys = self.table[i];
+                if phys != 0 {
+                    self.set_phys_used((phys - new_data_offset) / block_size, true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn read_from_alloc_table(
         &mut self,
         phys: u64,
@@ -811,35 +1317,200 @@ impl<B: StorageBackend> AeroSparseDisk<B> {
         let phys_off = phys
             .checked_add(offset_in_block as u64)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.read_at(phys_off, dst)
+        backend_read_at(&mut self.backend, phys_off, dst)
     }
 
+    /// Write into logical block `block_idx`, mapped at `phys`, keeping its checksum up to date.
     pub(crate) fn write_to_alloc_table(
         &mut self,
+        block_idx: u64,
         phys: u64,
         offset_in_block: usize,
         src: &[u8],
     ) -> Result<()> {
+        self.write_block_range(block_idx, phys, offset_in_block, BlockData::Bytes(src))
+    }
+
+    fn write_zeros_in_block(
+        &mut self,
+        block_idx: u64,
+        phys: u64,
+        offset_in_block: usize,
+        len: usize,
+    ) -> Result<()> {
+        self.write_block_range(block_idx, phys, offset_in_block, BlockData::Zeros(len))
+    }
+
+    fn write_raw(&mut self, phys: u64, offset_in_block: usize, data: BlockData<'_>) -> Result<()> {
         let phys_off = phys
             .checked_add(offset_in_block as u64)
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.write_at(phys_off, src)
+        match data {
+            BlockData::Bytes(src) => backend_write_at(&mut self.backend, phys_off, src),
+            BlockData::Zeros(len) => {
+                let mut done = 0usize;
+                while done < len {
+                    let chunk_len = (len - done).min(ZERO_BUF.len());
+                    backend_write_at(
+                        &mut self.backend,
+                        phys_off + done as u64,
+                        &ZERO_BUF[..chunk_len],
+                    )?;
+                    done += chunk_len;
+                }
+                Ok(())
+            }
+        }
     }
 
-    fn write_zeros_in_block(
+    fn write_block_range(
         &mut self,
+        block_idx: u64,
         phys: u64,
         offset_in_block: usize,
-        len: usize,
+        data: BlockData<'_>,
     ) -> Result<()> {
-        let mut remaining = len;
-        let mut off = offset_in_block;
-        while remaining > 0 {
-            let chunk_len = remaining.min(ZERO_BUF.len());
-            self.write_to_alloc_table(phys, off, &ZERO_BUF[..chunk_len])?;
+        let Some(old_crc) = self.stored_checksum(block_idx)? else {
+            return self.write_raw(phys, offset_in_block, data);
+        };
+
+        let block_size = self.header.block_size_u64();
+        let len = data.len();
+        let new_crc = if offset_in_block == 0 && len as u64 == block_size {
+            match data {
+                BlockData::Bytes(src) => crc32c(src),
+                BlockData::Zeros(_) => zero_block_crc(block_size),
+            }
+        } else {
+            // CRC32C is affine: the checksum changes by the raw CRC of `old ^ new` placed at the
+            // written offset of an otherwise zero block, so only the replaced bytes are read.
+            let mut delta = 0u32;
+            let mut old = [0u8; 4096];
+            let mut done = 0usize;
+            while done < len {
+                let chunk_len = (len - done).min(old.len());
+                self.read_from_alloc_table(phys, offset_in_block + done, &mut old[..chunk_len])?;
+                if let BlockData::Bytes(src) = data {
+                    for (o, n) in old[..chunk_len].iter_mut().zip(&src[done..]) {
+                        *o ^= n;
+                    }
+                }
+                delta = crc32c_raw(delta, &old[..chunk_len]);
+                done += chunk_len;
+            }
+            let trailing = block_size - (offset_in_block + len) as u64;
+            old_crc ^ crc32c_raw_zeros(delta, trailing)
+        };
+        if new_crc == old_crc {
+            return self.write_raw(phys, offset_in_block, data);
+        }
+
+        // Drop the checksum before the data changes and record the new one only once the data is
+        // written: a crash in between leaves the block without a checksum, never with a stale one.
+        self.set_checksum(block_idx, None)?;
+        self.write_raw(phys, offset_in_block, data)?;
+        self.set_checksum(block_idx, Some(new_crc))
+    }
+
+    fn stored_checksum(&self, block_idx: u64) -> Result<Option<u32>> {
+        let Some(sums) = self.checksums.as_ref() else {
+            return Ok(None);
+        };
+        let block_idx: usize = block_idx
+            .try_into()
+            .map_err(|_| DiskError::CorruptSparseImage("block index out of range"))?;
+        Ok(sums.crc(block_idx))
+    }
+
+    /// Update a checksum table entry in memory and on disk. No-op without block checksums.
+    fn set_checksum(&mut self, block_idx: u64, crc: Option<u32>) -> Result<()> {
+        let Some(sums) = self.checksums.as_mut() else {
+            return Ok(());
+        };
+        let block_idx_usize: usize = block_idx
+            .try_into()
+            .map_err(|_| DiskError::CorruptSparseImage("block index out of range"))?;
+        let slot = sums
+            .entries
+            .get_mut(block_idx_usize)
+            .ok_or(DiskError::CorruptSparseImage("block index out of range"))?;
+        let entry = crc.map_or(0, |crc| CHECKSUM_VALID | u64::from(crc));
+        if *slot == entry {
+            return Ok(());
+        }
+        *slot = entry;
+
+        let entry_off = self
+            .header
+            .checksum_table_offset()?
+            .checked_add(block_idx.checked_mul(8).ok_or(DiskError::OffsetOverflow)?)
+            .ok_or(DiskError::OffsetOverflow)?;
+        backend_write_at(&mut self.backend, entry_off, &entry.to_le_bytes())
+    }
+
+    fn set_verified(&mut self, block_idx: usize, verified: bool) {
+        if let Some(sums) = self.checksums.as_mut() {
+            sums.set_verified(block_idx, verified);
+        }
+    }
+
+    /// CRC32C of the whole block at `phys`, read in 64 KiB pieces.
+    fn block_crc(&mut self, phys: u64) -> Result<u32> {
+        let block_size: usize = self
+            .header
+            .block_size_u64()
+            .try_into()
+            .map_err(|_| DiskError::OffsetOverflow)?;
+        let mut buf = vec![0u8; block_size.min(64 * 1024)];
+        let mut crc = !0u32;
+        let mut off = 0usize;
+        while off < block_size {
+            let chunk_len = (block_size - off).min(buf.len());
+            self.read_from_alloc_table(phys, off, &mut buf[..chunk_len])?;
+            crc = crc32c_raw(crc, &buf[..chunk_len]);
             off += chunk_len;
-            remaining -= chunk_len;
         }
+        Ok(!crc)
+    }
+
+    /// Check a block against its checksum the first time it is read after opening the image.
+    fn verify_block(&mut self, block_idx: usize, phys: u64) -> Result<()> {
+        let Some(sums) = self.checksums.as_ref() else {
+            return Ok(());
+        };
+        if sums.is_verified(block_idx) {
+            return Ok(());
+        }
+        let Some(expected) = sums.crc(block_idx) else {
+            return Ok(());
+        };
+
+        let actual = self.block_crc(phys)?;
+        if actual != expected {
+            let block_idx = block_idx as u64;
+            let Some(sums) = self.checksums.as_mut() else {
+                return Ok(());
+            };
+            match sums.policy {
+                ChecksumMismatchPolicy::Error => {
+                    return Err(DiskError::ChecksumMismatch {
+                        block_idx,
+                        expected,
+                        actual,
+                    });
+                }
+                ChecksumMismatchPolicy::LogAndContinue => {
+                    tracing::warn!(
+                        block_idx,
+                        expected,
+                        actual,
+                        "aerosparse block checksum mismatch"
+                    );
+                    sums.mismatches += 1;
+                }
+            }
+        }
+        self.set_verified(block_idx, true);
         Ok(())
     }
 }
@@ -874,6 +1545,7 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for AeroSpars
             if phys == 0 {
                 buf[pos..pos + chunk_len].fill(0);
             } else {
+                self.verify_block(block_idx_usize, phys)?;
                 self.read_from_alloc_table(phys, within, &mut buf[pos..pos + chunk_len])?;
             }
 
@@ -919,16 +1591,17 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for AeroSpars
                 self.ensure_block_allocated(block_idx)?
             };
 
-            if !existed {
+            // New blocks of checksummed images are zero-filled on allocation.
+            if !existed && self.checksums.is_none() {
                 if within > 0 {
-                    self.write_zeros_in_block(phys, 0, within)?;
+                    self.write_zeros_in_block(block_idx, phys, 0, within)?;
                 }
                 let end = within + chunk_len;
                 if end < block_size_usize {
-                    self.write_zeros_in_block(phys, end, block_size_usize - end)?;
+                    self.write_zeros_in_block(block_idx, phys, end, block_size_usize - end)?;
                 }
             }
-            self.write_to_alloc_table(phys, within, &buf[pos..pos + chunk_len])?;
+            self.write_to_alloc_table(block_idx, phys, within, &buf[pos..pos + chunk_len])?;
 
             pos += chunk_len;
         }
@@ -937,10 +1610,41 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for AeroSpars
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.backend.flush()
+        self.backend
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::AeroSparse))
     }
 
     fn discard_range(&mut self, offset: u64, len: u64) -> Result<()> {
         AeroSparseDisk::discard_range(self, offset, len)
     }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        checked_range(
+            offset,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            self.capacity_bytes(),
+        )?;
+
+        let block_size = self.header.block_size_u64();
+        let end = offset + len;
+        let mut out = Vec::new();
+        let mut cur = offset;
+        while cur < end {
+            let block_idx = cur / block_size;
+            let chunk_end = (block_idx + 1).saturating_mul(block_size).min(end);
+            let status = if self.is_block_allocated(block_idx) {
+                AllocationStatus::Data
+            } else {
+                AllocationStatus::Unallocated
+            };
+            push_allocated_range(&mut out, cur, chunk_end - cur, status);
+            cur = chunk_end;
+        }
+        Ok(out)
+    }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        AeroSparseDisk::resize(self, new_capacity)
+    }
 }
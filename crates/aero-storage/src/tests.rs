@@ -65,6 +65,7 @@ fn make_header(
         * block_size_bytes as u64;
     AeroSparseHeader {
         version: 1,
+        flags: 0,
         block_size_bytes,
         disk_size_bytes,
         table_entries,
@@ -126,6 +127,7 @@ fn sparse_disk_reads_zero_until_allocated() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -152,6 +154,7 @@ fn sparse_disk_allocates_and_persists() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -181,6 +184,7 @@ fn sparse_disk_can_deallocate_blocks_and_read_zeros_afterwards() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -223,6 +227,7 @@ fn sparse_discard_range_only_deallocates_fully_covered_blocks() {
         AeroSparseConfig {
             disk_size_bytes: 4 * 4096,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -294,6 +299,7 @@ fn disk_image_discard_range_forwards_to_sparse_variant() {
         AeroSparseConfig {
             disk_size_bytes: capacity_bytes,
             block_size_bytes,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -399,7 +405,7 @@ fn block_cache_eviction_writeback_failure_keeps_dirty_block_resident() {
 
     // Force an eviction of block 0 (dirty). Write-back fails, so the operation errors out.
     let err = cached.read_at(32, &mut tmp).unwrap_err(); // block 2 would evict block 0
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
     assert_eq!(
         cached.stats().writebacks,
         0,
@@ -547,6 +553,7 @@ fn sparse_open_rejects_oversized_allocation_table() {
 
     let header = AeroSparseHeader {
         version: 1,
+        flags: 0,
         block_size_bytes,
         disk_size_bytes,
         table_entries,
@@ -580,6 +587,7 @@ fn sparse_open_allows_free_physical_block_slots() {
 
     let header = AeroSparseHeader {
         version: 1,
+        flags: 0,
         block_size_bytes,
         disk_size_bytes,
         table_entries,
@@ -658,6 +666,7 @@ fn sparse_create_rejects_block_size_too_large() {
         AeroSparseConfig {
             disk_size_bytes: 4096,
             block_size_bytes: 128 * 1024 * 1024,
+            ..Default::default()
         },
     ) {
         Ok(_) => panic!("expected create to fail"),
@@ -869,6 +878,7 @@ fn sparse_is_block_allocated_does_not_truncate_block_idx() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
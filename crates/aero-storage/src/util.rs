@@ -34,10 +34,129 @@ pub fn checked_range(offset: u64, len: usize, capacity: u64) -> Result<()> {
     Ok(())
 }
 
+/// Validate a [`crate::VirtualDisk::resize`] request.
+///
+/// Returns `Ok(false)` when the capacity is unchanged, `Ok(true)` when the disk needs to grow, and
+/// an error for shrink requests or capacities that are not a whole number of sectors.
+pub fn check_grow(current: u64, new_capacity: u64) -> Result<bool> {
+    if new_capacity < current {
+        return Err(DiskError::Unsupported("shrinking a disk"));
+    }
+    if new_capacity == current {
+        return Ok(false);
+    }
+    if !new_capacity.is_multiple_of(crate::SECTOR_SIZE as u64) {
+        return Err(DiskError::InvalidConfig(
+            "disk capacity must be a multiple of 512",
+        ));
+    }
+    Ok(true)
+}
+
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32C_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC32C (Castagnoli) of `bytes`.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    !crc32c_raw(!0, bytes)
+}
+
+/// Feed `bytes` into a CRC32C register without the initial/final inversion.
+pub fn crc32c_raw(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &b in bytes {
+        crc = CRC32C_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// Feed `len` zero bytes into a CRC32C register in `O(log len)` steps.
+///
+/// The register update is linear over GF(2), so the operator for one zero bit is squared up to one
+/// byte and then to each power-of-two length (as in zlib's `crc32_combine`).
+pub fn crc32c_raw_zeros(mut crc: u32, mut len: u64) -> u32 {
+    fn times(mat: &[u32; 32], mut vec: u32) -> u32 {
+        let mut sum = 0;
+        let mut i = 0;
+        while vec != 0 {
+            if vec & 1 != 0 {
+                sum ^= mat[i];
+            }
+            vec >>= 1;
+            i += 1;
+        }
+        sum
+    }
+    fn square(mat: &[u32; 32]) -> [u32; 32] {
+        let mut out = [0u32; 32];
+        for (n, col) in out.iter_mut().enumerate() {
+            *col = times(mat, mat[n]);
+        }
+        out
+    }
+
+    let mut op = [0u32; 32];
+    op[0] = CRC32C_POLY;
+    for (n, col) in op.iter_mut().enumerate().skip(1) {
+        *col = 1 << (n - 1);
+    }
+    for _ in 0..3 {
+        op = square(&op);
+    }
+    while len != 0 {
+        if len & 1 != 0 {
+            crc = times(&op, crc);
+        }
+        len >>= 1;
+        if len != 0 {
+            op = square(&op);
+        }
+    }
+    crc
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn crc32c_matches_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+        assert_eq!(crc32c(b""), 0);
+    }
+
+    #[test]
+    fn crc32c_raw_zeros_matches_feeding_zero_bytes() {
+        for len in [0usize, 1, 7, 8, 511, 4096, 65_537] {
+            let zeros = vec![0u8; len];
+            for init in [0u32, !0, 0x1234_5678] {
+                assert_eq!(
+                    crc32c_raw_zeros(init, len as u64),
+                    crc32c_raw(init, &zeros),
+                    "len={len} init={init:#x}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn align_up_u64_supports_non_power_of_two_alignments() {
         assert_eq!(align_up_u64(0, 10).unwrap(), 0);
@@ -1,9 +1,14 @@
 use lru::LruCache;
+use serde::Serialize;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 
-use crate::util::{align_up_u64, checked_range};
-use crate::{DiskError, Result, StorageBackend, VirtualDisk, SECTOR_SIZE};
+use crate::disk::push_allocated_range;
+use crate::util::{align_up_u64, check_grow, checked_range};
+use crate::{
+    AllocatedRange, AllocationStatus, DiskError, DiskLayer, Result, StorageBackend, VirtualDisk,
+    SECTOR_SIZE,
+};
 
 const VHD_FOOTER_COOKIE: [u8; 8] = *b"conectix";
 const VHD_DYNAMIC_COOKIE: [u8; 8] = *b"cxsparse";
@@ -29,6 +34,10 @@ const MAX_BLOCK_SIZE_BYTES: u32 = 64 * 1024 * 1024; // 64 MiB
 // for synchronous backends like OPFS handles).
 const VHD_TABLE_READ_CHUNK_BYTES: usize = 8 * 1024 * 1024; // 8 MiB
 
+// Parent locator payloads are file paths; cap them well above any real path length.
+const MAX_PARENT_LOCATOR_BYTES: u32 = 64 * 1024;
+const VHD_PARENT_LOCATOR_ENTRIES: usize = 8;
+
 // Bound bitmap caching when reading large fully-allocated dynamic VHDs.
 const VHD_BITMAP_CACHE_BUDGET_BYTES: u64 = 16 * 1024 * 1024; // 16 MiB
 
@@ -156,6 +165,329 @@ impl VhdDynamicHeader {
     }
 }
 
+/// Identity of a VHD image, as recorded in its footer.
+///
+/// A differencing disk stores the identity of its parent in its dynamic header; see
+/// [`VhdDifferencingOptions::parent_identity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhdIdentity {
+    pub unique_id: [u8; 16],
+    /// Seconds since 2000-01-01 00:00:00 UTC.
+    pub timestamp: u32,
+}
+
+/// One parent locator entry from a differencing VHD's dynamic header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdParentLocator {
+    /// Platform code, e.g. `W2ru` (Windows relative path) or `W2ku` (Windows absolute path).
+    pub platform_code: [u8; 4],
+    /// Decoded locator payload for the path-based platform codes (`W2ru`, `W2ku`, `Wi2r`, `Wi2k`,
+    /// `MacX`), or `None` for codes we do not interpret.
+    pub path: Option<String>,
+}
+
+/// Parent metadata recorded in a differencing VHD.
+///
+/// Aero never opens the parent itself: hosts use this to locate the parent image (typically via
+/// [`VhdParentInfo::preferred_path`]) and then pass it to [`VhdDisk::open_differencing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhdParentInfo {
+    /// Expected parent identity (unique id + modification timestamp).
+    pub identity: VhdIdentity,
+    /// Parent file name (the dynamic header "parent unicode name").
+    pub name: String,
+    pub locators: Vec<VhdParentLocator>,
+}
+
+impl VhdParentInfo {
+    /// Best path hint for the parent: a relative Windows path, then an absolute Windows path,
+    /// then a Mac URL, then any other decoded locator.
+    pub fn preferred_path(&self) -> Option<&str> {
+        const PREFERENCE: [&[u8; 4]; 3] = [b"W2ru", b"W2ku", b"MacX"];
+        PREFERENCE
+            .iter()
+            .find_map(|code| {
+                self.locators
+                    .iter()
+                    .find(|l| &l.platform_code == *code)
+                    .and_then(|l| l.path.as_deref())
+            })
+            .or_else(|| self.locators.iter().find_map(|l| l.path.as_deref()))
+    }
+
+    fn parse<B: StorageBackend>(raw: &[u8; 1024], backend: &mut B) -> Result<Self> {
+        let mut unique_id = [0u8; 16];
+        unique_id.copy_from_slice(&raw[40..56]);
+        let identity = VhdIdentity {
+            unique_id,
+            timestamp: be_u32(&raw[56..60]),
+        };
+
+        let name_units: Vec<u16> = raw[64..576]
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .take_while(|&u| u != 0)
+            .collect();
+        let name = String::from_utf16_lossy(&name_units);
+
+        let len = backend.len()?;
+        let mut locators = Vec::new();
+        for i in 0..VHD_PARENT_LOCATOR_ENTRIES {
+            let entry = &raw[576 + i * 24..576 + (i + 1) * 24];
+            let platform_code: [u8; 4] = [entry[0], entry[1], entry[2], entry[3]];
+            if platform_code == [0; 4] {
+                continue;
+            }
+            let data_len = be_u32(&entry[8..12]);
+            let data_offset = be_u64(&entry[16..24]);
+            if data_len > MAX_PARENT_LOCATOR_BYTES {
+                return Err(DiskError::CorruptImage("vhd parent locator too large"));
+            }
+            let end = data_offset
+                .checked_add(data_len as u64)
+                .ok_or(DiskError::OffsetOverflow)?;
+            if end > len {
+                return Err(DiskError::CorruptImage("vhd parent locator truncated"));
+            }
+
+            let path = match &platform_code {
+                b"W2ru" | b"W2ku" | b"Wi2r" | b"Wi2k" | b"MacX" => {
+                    let mut data = vec![0u8; data_len as usize];
+                    backend.read_at(data_offset, &mut data)?;
+                    Some(decode_locator_path(&platform_code, &data))
+                }
+                _ => None,
+            };
+            locators.push(VhdParentLocator {
+                platform_code,
+                path,
+            });
+        }
+
+        Ok(Self {
+            identity,
+            name,
+            locators,
+        })
+    }
+}
+
+/// Options for [`VhdDisk::open_differencing_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhdDifferencingOptions {
+    /// Identity of the supplied parent (e.g. from [`VhdDisk::identity`]). When set, it must match
+    /// the parent identity recorded in the child.
+    pub parent_identity: Option<VhdIdentity>,
+    /// Also require the parent timestamp to match. Copying or re-saving a parent often changes
+    /// its timestamp without changing its contents, so callers may want to relax this.
+    pub check_timestamp: bool,
+}
+
+impl Default for VhdDifferencingOptions {
+    fn default() -> Self {
+        Self {
+            parent_identity: None,
+            check_timestamp: true,
+        }
+    }
+}
+
+/// Which of the two footer sectors a [`VhdProblem`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VhdFooterLocation {
+    /// The required footer in the last sector of the file.
+    Trailing,
+    /// The copy in the first sector (required for dynamic and differencing disks, optional for
+    /// fixed ones).
+    Copy,
+}
+
+impl VhdFooterLocation {
+    fn offset(self, file_len: u64) -> u64 {
+        match self {
+            VhdFooterLocation::Trailing => file_len - SECTOR_SIZE as u64,
+            VhdFooterLocation::Copy => 0,
+        }
+    }
+}
+
+/// One problem found by [`VhdDisk::validate`].
+///
+/// Serializes as an object tagged with `kind`, e.g.
+/// `{"kind": "batEntryOutOfRange", "index": 3, "sector": 4096}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum VhdProblem {
+    /// The file is shorter than one sector, or not a whole number of sectors long.
+    FileLengthInvalid {
+        len: u64,
+    },
+    FooterCookieMissing {
+        location: VhdFooterLocation,
+    },
+    FooterChecksumMismatch {
+        location: VhdFooterLocation,
+        stored: u32,
+        computed: u32,
+    },
+    /// Both footers are intact but differ; dynamic and differencing disks keep identical copies.
+    FooterCopyMismatch,
+    /// A footer field is unusable (bad version, size or data offset). Checking stops here.
+    FooterInvalid {
+        location: VhdFooterLocation,
+        reason: &'static str,
+    },
+    DiskTypeUnsupported {
+        disk_type: u32,
+    },
+    /// The recorded CHS geometry addresses more sectors than `current_size` holds.
+    GeometryMismatch {
+        cylinders: u16,
+        heads: u8,
+        sectors_per_track: u8,
+        current_size: u64,
+    },
+    FixedDiskTruncated {
+        len: u64,
+        required_len: u64,
+    },
+    /// The footer's data offset does not leave room for a dynamic header between the footers.
+    DynamicHeaderOutOfBounds {
+        offset: u64,
+    },
+    DynamicHeaderCookieMissing {
+        offset: u64,
+    },
+    DynamicHeaderChecksumMismatch {
+        stored: u32,
+        computed: u32,
+    },
+    /// A dynamic header field other than the cookie and checksum is unusable.
+    DynamicHeaderInvalid {
+        reason: &'static str,
+    },
+    /// `max_table_entries` cannot map `current_size`.
+    BatTooSmall {
+        max_table_entries: u32,
+        required_entries: u64,
+    },
+    /// The BAT overlaps the footers or the dynamic header, or runs past the end of the file.
+    BatOutOfBounds {
+        table_offset: u64,
+        table_bytes: u64,
+        file_len: u64,
+    },
+    /// BAT entry `index` points (in sectors) at a block that overlaps metadata or runs past the
+    /// trailing footer (the end of the file, if the footer is missing).
+    BatEntryOutOfRange {
+        index: u64,
+        sector: u32,
+    },
+    /// The blocks of BAT entries `first` and `second` overlap.
+    BlocksOverlap {
+        first: u64,
+        second: u64,
+    },
+}
+
+/// At most this many [`VhdProblem::BatEntryOutOfRange`] / [`VhdProblem::BlocksOverlap`] entries
+/// are listed in a [`VhdValidationReport`]; the full count is still recorded.
+pub const VHD_MAX_REPORTED_BAT_PROBLEMS: usize = 64;
+
+/// Result of [`VhdDisk::validate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VhdValidationReport {
+    pub file_len: u64,
+    /// Disk type of the footer the checks used (2 fixed, 3 dynamic, 4 differencing), if any
+    /// footer was usable.
+    pub disk_type: Option<u32>,
+    pub current_size: Option<u64>,
+    /// Problems in on-disk order: footers, then the dynamic header, then the BAT.
+    pub problems: Vec<VhdProblem>,
+    /// Number of BAT entries pointing outside the data region, including those beyond
+    /// [`VHD_MAX_REPORTED_BAT_PROBLEMS`].
+    pub bat_entries_out_of_range: u64,
+}
+
+impl VhdValidationReport {
+    /// `true` if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn push_bat_problem(&mut self, reported: &mut usize, problem: VhdProblem) {
+        if *reported < VHD_MAX_REPORTED_BAT_PROBLEMS {
+            self.problems.push(problem);
+            *reported += 1;
+        }
+    }
+}
+
+/// What [`VhdDisk::repair`] may change. Everything is enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VhdRepairPolicy {
+    /// Rewrite a damaged or missing footer from the other, intact one (the trailing footer from
+    /// the copy at offset 0, or the copy from the trailing footer on dynamic/differencing disks).
+    ///
+    /// A trailing footer lost to truncation is written right after the data (the last allocated
+    /// block of a dynamic/differencing disk), resizing the file to end with it.
+    pub restore_footers: bool,
+    /// Rewrite footer and dynamic header checksums that do not match otherwise usable contents.
+    pub recompute_checksums: bool,
+    /// Mark BAT entries that point outside the data region as unallocated. The data they pointed
+    /// at is lost (reads return zeros, or the parent's data for differencing disks).
+    pub clamp_bat_entries: bool,
+}
+
+impl Default for VhdRepairPolicy {
+    fn default() -> Self {
+        Self {
+            restore_footers: true,
+            recompute_checksums: true,
+            clamp_bat_entries: true,
+        }
+    }
+}
+
+/// One change made by [`VhdDisk::repair`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum VhdRepairAction {
+    /// Rewrote the footer at `location` from the other one.
+    RestoredFooter {
+        location: VhdFooterLocation,
+    },
+    RecomputedFooterChecksum {
+        location: VhdFooterLocation,
+    },
+    RecomputedDynamicHeaderChecksum,
+    /// Marked `count` out-of-range BAT entries as unallocated.
+    ClampedBatEntries {
+        count: u64,
+    },
+}
+
+/// Result of [`VhdDisk::repair`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VhdRepairReport {
+    /// Validation before any changes.
+    pub before: VhdValidationReport,
+    pub actions: Vec<VhdRepairAction>,
+    /// Validation after the changes; problems left here were not repairable under the policy.
+    pub after: VhdValidationReport,
+}
+
 /// VHD fixed/dynamic disk (subset).
 ///
 /// Supported:
@@ -167,10 +499,11 @@ impl VhdDynamicHeader {
 ///   - Dynamic header + BAT + per-block bitmaps
 ///
 /// Unsupported:
-/// - Any features that require interpreting backing chains automatically (e.g. resolving parent
-///   locators to filesystem paths). Differencing disks (`disk_type=4`) are supported only via
-///   [`VhdDisk::open_differencing`] (or [`VhdDisk::open_with_parent`]) with an explicitly supplied
-///   parent disk.
+/// - Resolving parent locators to filesystem paths automatically. Differencing disks
+///   (`disk_type=4`) are supported only via [`VhdDisk::open_differencing`] (or
+///   [`VhdDisk::open_with_parent`]) with an explicitly supplied parent disk; the recorded locators
+///   are exposed through [`VhdDisk::read_parent_info`] / [`VhdDisk::parent_info`] so callers can
+///   resolve the parent themselves.
 pub struct VhdDisk<B> {
     backend: B,
     footer: VhdFooter,
@@ -179,6 +512,7 @@ pub struct VhdDisk<B> {
     bitmap_cache: LruCache<u64, Arc<Vec<u8>>>,
     fixed_data_offset: u64,
     parent: Option<Box<dyn VirtualDisk>>,
+    parent_info: Option<VhdParentInfo>,
 }
 
 impl<B: StorageBackend> VhdDisk<B> {
@@ -248,6 +582,7 @@ impl<B: StorageBackend> VhdDisk<B> {
                     bitmap_cache: LruCache::new(NonZeroUsize::MIN),
                     fixed_data_offset,
                     parent: None,
+                    parent_info: None,
                 })
             }
             VHD_DISK_TYPE_DYNAMIC => {
@@ -343,49 +678,7 @@ impl<B: StorageBackend> VhdDisk<B> {
 
                 // Only read the BAT entries needed for the virtual size; this avoids allocating
                 // memory proportional to `max_table_entries` for sparse/truncated images.
-                let bat_bytes = required_entries
-                    .checked_mul(4)
-                    .ok_or(DiskError::OffsetOverflow)?;
-                if bat_bytes > MAX_BAT_BYTES {
-                    return Err(DiskError::Unsupported("vhd bat too large"));
-                }
-                let entries: usize = required_entries
-                    .try_into()
-                    .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-                let bat_bytes_usize: usize = bat_bytes
-                    .try_into()
-                    .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-
-                // Read the BAT without allocating an additional full-size temporary buffer.
-                let mut bat = Vec::new();
-                bat.try_reserve_exact(entries)
-                    .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-
-                let mut buf = Vec::new();
-                let buf_len = bat_bytes_usize.min(VHD_TABLE_READ_CHUNK_BYTES);
-                buf.try_reserve_exact(buf_len)
-                    .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-                buf.resize(buf_len, 0);
-
-                let mut remaining = bat_bytes_usize;
-                let mut off = dynamic.table_offset;
-                while remaining > 0 {
-                    let read_len = remaining.min(buf.len());
-                    match backend.read_at(off, &mut buf[..read_len]) {
-                        Ok(()) => {}
-                        Err(DiskError::OutOfBounds { .. }) => {
-                            return Err(DiskError::CorruptImage("vhd bat truncated"));
-                        }
-                        Err(e) => return Err(e),
-                    }
-                    for chunk in buf[..read_len].chunks_exact(4) {
-                        bat.push(be_u32(chunk));
-                    }
-                    off = off
-                        .checked_add(read_len as u64)
-                        .ok_or(DiskError::OffsetOverflow)?;
-                    remaining -= read_len;
-                }
+                let bat = read_bat(&mut backend, dynamic.table_offset, required_entries)?;
 
                 // Size bitmap caching based on the bitmap size for this image.
                 let sectors_per_block = (dynamic.block_size as u64) / SECTOR_SIZE as u64;
@@ -466,6 +759,7 @@ impl<B: StorageBackend> VhdDisk<B> {
                     bitmap_cache: LruCache::new(cap),
                     fixed_data_offset: 0,
                     parent: None,
+                    parent_info: None,
                 })
             }
             VHD_DISK_TYPE_DIFFERENCING => Err(DiskError::Unsupported(
@@ -558,6 +852,7 @@ impl<B: StorageBackend> VhdDisk<B> {
             Err(e) => return Err(e),
         }
         let dynamic = VhdDynamicHeader::parse(&raw_header)?;
+        let parent_info = VhdParentInfo::parse(&raw_header, &mut backend)?;
 
         let required_entries = footer.current_size.div_ceil(dynamic.block_size as u64);
         if (dynamic.max_table_entries as u64) < required_entries {
@@ -588,48 +883,7 @@ impl<B: StorageBackend> VhdDisk<B> {
             return Err(DiskError::CorruptImage("vhd bat overlaps dynamic header"));
         }
 
-        let bat_bytes = required_entries
-            .checked_mul(4)
-            .ok_or(DiskError::OffsetOverflow)?;
-        if bat_bytes > MAX_BAT_BYTES {
-            return Err(DiskError::Unsupported("vhd bat too large"));
-        }
-        let entries: usize = required_entries
-            .try_into()
-            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-        let bat_bytes_usize: usize = bat_bytes
-            .try_into()
-            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-
-        let mut bat = Vec::new();
-        bat.try_reserve_exact(entries)
-            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-
-        let mut buf = Vec::new();
-        let buf_len = bat_bytes_usize.min(VHD_TABLE_READ_CHUNK_BYTES);
-        buf.try_reserve_exact(buf_len)
-            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
-        buf.resize(buf_len, 0);
-
-        let mut remaining = bat_bytes_usize;
-        let mut off = dynamic.table_offset;
-        while remaining > 0 {
-            let read_len = remaining.min(buf.len());
-            match backend.read_at(off, &mut buf[..read_len]) {
-                Ok(()) => {}
-                Err(DiskError::OutOfBounds { .. }) => {
-                    return Err(DiskError::CorruptImage("vhd bat truncated"));
-                }
-                Err(e) => return Err(e),
-            }
-            for chunk in buf[..read_len].chunks_exact(4) {
-                bat.push(be_u32(chunk));
-            }
-            off = off
-                .checked_add(read_len as u64)
-                .ok_or(DiskError::OffsetOverflow)?;
-            remaining -= read_len;
-        }
+        let bat = read_bat(&mut backend, dynamic.table_offset, required_entries)?;
 
         let sectors_per_block = (dynamic.block_size as u64) / SECTOR_SIZE as u64;
         let bitmap_bytes = sectors_per_block.div_ceil(8);
@@ -700,9 +954,78 @@ impl<B: StorageBackend> VhdDisk<B> {
             bitmap_cache: LruCache::new(cap),
             fixed_data_offset: 0,
             parent: Some(parent),
+            parent_info: Some(parent_info),
         })
     }
 
+    /// Like [`VhdDisk::open_differencing`], additionally checking the supplied parent against the
+    /// parent identity recorded in the child.
+    pub fn open_differencing_with_options(
+        backend: B,
+        parent: Box<dyn VirtualDisk>,
+        options: VhdDifferencingOptions,
+    ) -> Result<Self> {
+        let disk = Self::open_differencing(backend, parent)?;
+        if let (Some(expected), Some(actual)) = (
+            disk.parent_info.as_ref().map(|info| info.identity),
+            options.parent_identity,
+        ) {
+            if expected.unique_id != actual.unique_id {
+                return Err(DiskError::InvalidConfig(
+                    "vhd parent unique id does not match differencing disk",
+                ));
+            }
+            if options.check_timestamp && expected.timestamp != actual.timestamp {
+                return Err(DiskError::InvalidConfig(
+                    "vhd parent timestamp does not match differencing disk",
+                ));
+            }
+        }
+        Ok(disk)
+    }
+
+    /// Read the parent metadata of a differencing VHD without opening it.
+    ///
+    /// Hosts use this to resolve the parent image before calling
+    /// [`VhdDisk::open_differencing`]. Returns `InvalidConfig` for non-differencing images.
+    pub fn read_parent_info(backend: &mut B) -> Result<VhdParentInfo> {
+        let len = backend.len()?;
+        if len < SECTOR_SIZE as u64 {
+            return Err(DiskError::CorruptImage("vhd file too small"));
+        }
+        let mut raw_footer = [0u8; SECTOR_SIZE];
+        backend.read_at(len - SECTOR_SIZE as u64, &mut raw_footer)?;
+        let footer = VhdFooter::parse(raw_footer)?;
+        if footer.disk_type != VHD_DISK_TYPE_DIFFERENCING {
+            return Err(DiskError::InvalidConfig("vhd is not a differencing disk"));
+        }
+        let mut raw_header = [0u8; 1024];
+        match backend.read_at(footer.data_offset, &mut raw_header) {
+            Ok(()) => {}
+            Err(DiskError::OutOfBounds { .. }) => {
+                return Err(DiskError::CorruptImage("vhd dynamic header truncated"));
+            }
+            Err(e) => return Err(e),
+        }
+        VhdDynamicHeader::parse(&raw_header)?;
+        VhdParentInfo::parse(&raw_header, backend)
+    }
+
+    /// Parent metadata for differencing disks, `None` otherwise.
+    pub fn parent_info(&self) -> Option<&VhdParentInfo> {
+        self.parent_info.as_ref()
+    }
+
+    /// This image's own identity, for checking it as the parent of a differencing disk.
+    pub fn identity(&self) -> VhdIdentity {
+        let mut unique_id = [0u8; 16];
+        unique_id.copy_from_slice(&self.footer.raw[68..84]);
+        VhdIdentity {
+            unique_id,
+            timestamp: be_u32(&self.footer.raw[24..28]),
+        }
+    }
+
     pub fn into_backend(self) -> B {
         self.backend
     }
@@ -717,11 +1040,110 @@ impl<B: StorageBackend> VhdDisk<B> {
         Self::open_differencing(backend, parent)
     }
 
+    /// Check an image's metadata without opening it.
+    ///
+    /// Unlike [`VhdDisk::open`], which stops at the first inconsistency, this keeps going where it
+    /// can and lists every problem found: footer cookies and checksums, the footer copy, CHS
+    /// geometry against `current_size`, fixed disk length, the dynamic header, BAT placement and
+    /// (for dynamic and differencing disks) every BAT entry. Only I/O failures and images beyond
+    /// this implementation's limits are returned as errors.
+    pub fn validate(backend: &mut B) -> Result<VhdValidationReport> {
+        Ok(inspect(backend)?.report)
+    }
+
+    /// Fix what `policy` allows in place, then validate again.
+    ///
+    /// Repairs are written to `backend` and flushed. Differencing disks can be repaired too, but
+    /// must then be opened with [`VhdDisk::open_differencing`].
+    pub fn repair(backend: &mut B, policy: VhdRepairPolicy) -> Result<VhdRepairReport> {
+        let inspection = inspect(backend)?;
+        let len = inspection.report.file_len;
+        let mut actions = Vec::new();
+
+        if let Some(footer) = inspection.footer.as_ref().filter(|f| f.fields_valid) {
+            let mut raw = footer.raw;
+            let mut intact = footer.intact;
+            if !intact && policy.recompute_checksums {
+                raw = with_footer_checksum(raw);
+                backend_write_at(backend, footer.location.offset(len), &raw)?;
+                actions.push(VhdRepairAction::RecomputedFooterChecksum {
+                    location: footer.location,
+                });
+                intact = true;
+            }
+
+            if intact && policy.restore_footers {
+                let sparse = footer.disk_type != VHD_DISK_TYPE_FIXED;
+                if let Some(offset) = inspection.trailing_footer_offset.filter(|_| {
+                    footer.location == VhdFooterLocation::Copy && inspection.trailing != Some(raw)
+                }) {
+                    // A cut-short file is extended (or trailing garbage dropped) so the footer
+                    // ends up in the last sector, right after the data.
+                    let end = offset + SECTOR_SIZE as u64;
+                    if end != len {
+                        backend_set_len(backend, end)?;
+                    }
+                    let location = VhdFooterLocation::Trailing;
+                    backend_write_at(backend, offset, &raw)?;
+                    actions.push(VhdRepairAction::RestoredFooter { location });
+                }
+                if footer.location == VhdFooterLocation::Trailing
+                    && sparse
+                    && inspection.copy != Some(raw)
+                {
+                    let location = VhdFooterLocation::Copy;
+                    backend_write_at(backend, location.offset(len), &raw)?;
+                    actions.push(VhdRepairAction::RestoredFooter { location });
+                }
+            }
+        }
+
+        if let Some(header) = &inspection.dynamic_header {
+            if !header.intact && policy.recompute_checksums {
+                let raw = with_dynamic_header_checksum(header.raw);
+                backend_write_at(backend, header.offset, &raw)?;
+                actions.push(VhdRepairAction::RecomputedDynamicHeaderChecksum);
+            }
+        }
+
+        if policy.clamp_bat_entries && !inspection.bad_bat_entries.is_empty() {
+            for &index in &inspection.bad_bat_entries {
+                let offset = inspection
+                    .table_offset
+                    .checked_add(index * 4)
+                    .ok_or(DiskError::OffsetOverflow)?;
+                backend_write_at(backend, offset, &u32::MAX.to_be_bytes())?;
+            }
+            let count = inspection.bad_bat_entries.len() as u64;
+            tracing::warn!(count, "vhd: marked out-of-range BAT entries unallocated");
+            actions.push(VhdRepairAction::ClampedBatEntries { count });
+        }
+
+        if !actions.is_empty() {
+            backend.flush().map_err(|e| e.in_layer(DiskLayer::Vhd))?;
+        }
+
+        Ok(VhdRepairReport {
+            before: inspection.report,
+            actions,
+            after: Self::validate(backend)?,
+        })
+    }
+
+    /// [`VhdDisk::repair`] the image, then [`VhdDisk::open`] it.
+    ///
+    /// Fails like `open` if problems the policy could not repair remain.
+    pub fn open_repair(mut backend: B, policy: VhdRepairPolicy) -> Result<(Self, VhdRepairReport)> {
+        let report = Self::repair(&mut backend, policy)?;
+        Ok((Self::open(backend)?, report))
+    }
+
     fn backend_read_at(&mut self, offset: u64, buf: &mut [u8], ctx: &'static str) -> Result<()> {
+        let len = buf.len();
         match self.backend.read_at(offset, buf) {
             Ok(()) => Ok(()),
             Err(DiskError::OutOfBounds { .. }) => Err(DiskError::CorruptImage(ctx)),
-            Err(e) => Err(e),
+            Err(e) => Err(e.with_context(DiskLayer::Vhd, offset, len)),
         }
     }
 
@@ -836,7 +1258,7 @@ impl<B: StorageBackend> VhdDisk<B> {
         }
 
         // Prevent allocated blocks from overlapping the required footer at EOF.
-        let file_len = self.backend.len()?;
+        let file_len = backend_len(&mut self.backend)?;
         if file_len < SECTOR_SIZE as u64 {
             return Err(DiskError::CorruptImage("vhd file truncated"));
         }
@@ -854,6 +1276,25 @@ impl<B: StorageBackend> VhdDisk<B> {
         Ok(())
     }
 
+    /// Report `[offset, offset + len)`, which is not stored in this image, as the parent's
+    /// allocation (differencing disks) or as unallocated.
+    fn push_unstored_range(
+        &mut self,
+        out: &mut Vec<AllocatedRange>,
+        offset: u64,
+        len: u64,
+    ) -> Result<()> {
+        match self.parent.as_mut() {
+            Some(parent) => {
+                for r in parent.allocation_status(offset, len)? {
+                    push_allocated_range(out, r.offset, r.len, r.status);
+                }
+            }
+            None => push_allocated_range(out, offset, len, AllocationStatus::Unallocated),
+        }
+        Ok(())
+    }
+
     fn is_sector_allocated(&mut self, lba: u64) -> Result<bool> {
         let (sectors_per_block, bitmap_size) = self.dyn_params()?;
         let block_index_u64 = lba / sectors_per_block;
@@ -895,7 +1336,10 @@ impl<B: StorageBackend> VhdDisk<B> {
                 let off = lba
                     .checked_mul(SECTOR_SIZE as u64)
                     .ok_or(DiskError::OffsetOverflow)?;
-                return parent.read_at(off, out);
+                let len = out.len();
+                return parent
+                    .read_at(off, out)
+                    .map_err(|e| e.with_context(DiskLayer::Vhd, off, len));
             }
             out.fill(0);
             return Ok(());
@@ -911,7 +1355,10 @@ impl<B: StorageBackend> VhdDisk<B> {
                 let off = lba
                     .checked_mul(SECTOR_SIZE as u64)
                     .ok_or(DiskError::OffsetOverflow)?;
-                return parent.read_at(off, out);
+                let len = out.len();
+                return parent
+                    .read_at(off, out)
+                    .map_err(|e| e.with_context(DiskLayer::Vhd, off, len));
             }
             out.fill(0);
             return Ok(());
@@ -961,7 +1408,7 @@ impl<B: StorageBackend> VhdDisk<B> {
             .checked_add(bitmap_size)
             .and_then(|v| v.checked_add(sector_in_block * SECTOR_SIZE as u64))
             .ok_or(DiskError::OffsetOverflow)?;
-        self.backend.write_at(data_offset, data)?;
+        backend_write_at(&mut self.backend, data_offset, data)?;
 
         // Update the per-block bitmap.
         //
@@ -999,7 +1446,7 @@ impl<B: StorageBackend> VhdDisk<B> {
         };
 
         if new_byte != old_byte {
-            if let Err(e) = self.backend.write_at(byte_offset, &[new_byte]) {
+            if let Err(e) = backend_write_at(&mut self.backend, byte_offset, &[new_byte]) {
                 // Best-effort rollback so a failed write doesn't leave the in-memory bitmap
                 // claiming the sector is present when the on-disk bitmap was not updated.
                 if let Some(entry) = self.bitmap_cache.get_mut(&block_start) {
@@ -1028,7 +1475,7 @@ impl<B: StorageBackend> VhdDisk<B> {
             return Err(DiskError::CorruptImage("vhd block already allocated"));
         }
 
-        let file_len = self.backend.len()?;
+        let file_len = backend_len(&mut self.backend)?;
         if file_len < SECTOR_SIZE as u64 {
             return Err(DiskError::CorruptImage("vhd file truncated"));
         }
@@ -1047,14 +1494,14 @@ impl<B: StorageBackend> VhdDisk<B> {
             .checked_add(SECTOR_SIZE as u64)
             .ok_or(DiskError::OffsetOverflow)?;
 
-        self.backend.set_len(new_len)?;
+        backend_set_len(&mut self.backend, new_len)?;
 
         // For dynamic VHDs, the footer must always exist at the end of the file. We are about to
         // overwrite the old footer (at `old_footer_offset`) with the new block's bitmap, so write
         // the footer to its new location *first*. If this write fails, roll back the resize so
         // the original footer remains at EOF.
         self.footer.rewrite_checksum();
-        if let Err(e) = self.backend.write_at(new_footer_offset, &self.footer.raw) {
+        if let Err(e) = backend_write_at(&mut self.backend, new_footer_offset, &self.footer.raw) {
             let _ = self.backend.set_len(file_len);
             return Err(e);
         }
@@ -1111,6 +1558,155 @@ impl<B: StorageBackend> VhdDisk<B> {
 
         Ok(old_footer_offset)
     }
+
+    /// Grow a dynamic VHD to `new_capacity` bytes.
+    ///
+    /// The BAT is extended in place. Its sector padding often has room for the extra entries;
+    /// otherwise any data blocks sitting directly after the BAT are moved to the end of the file
+    /// first. The footer (both copies) is rewritten last, so an interrupted resize leaves a valid
+    /// image with the old virtual size.
+    fn grow_dynamic(&mut self, new_capacity: u64) -> Result<()> {
+        let dyn_hdr = self
+            .dynamic
+            .clone()
+            .ok_or(DiskError::CorruptImage("vhd is not dynamic"))?;
+        let (_, bitmap_size) = self.dyn_params()?;
+        let block_size = dyn_hdr.block_size as u64;
+
+        let new_entries = new_capacity.div_ceil(block_size);
+        let new_entries_u32: u32 = new_entries
+            .try_into()
+            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+        let new_entries_usize: usize = new_entries
+            .try_into()
+            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+        self.bat
+            .try_reserve_exact(new_entries_usize.saturating_sub(self.bat.len()))
+            .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+
+        let max_entries = (dyn_hdr.max_table_entries as u64).max(new_entries);
+        let bat_size = align_up_u64(max_entries * 4, SECTOR_SIZE as u64)?;
+        if bat_size > MAX_BAT_BYTES {
+            return Err(DiskError::Unsupported("vhd bat too large"));
+        }
+        let bat_end = dyn_hdr
+            .table_offset
+            .checked_add(bat_size)
+            .ok_or(DiskError::OffsetOverflow)?;
+
+        if max_entries > dyn_hdr.max_table_entries as u64 {
+            let old_bat_end = self.data_region_start()?;
+            if bat_end > old_bat_end {
+                let dyn_header_end = self.footer.data_offset + 1024;
+                if self.footer.data_offset < bat_end && dyn_header_end > dyn_hdr.table_offset {
+                    return Err(DiskError::Unsupported("vhd bat cannot grow in place"));
+                }
+                self.evacuate_blocks_below(bat_end, bitmap_size, &dyn_hdr)?;
+
+                let file_len = backend_len(&mut self.backend)?;
+                let footer_offset = file_len - SECTOR_SIZE as u64;
+                if footer_offset < bat_end {
+                    backend_set_len(&mut self.backend, bat_end + SECTOR_SIZE as u64)?;
+                    backend_write_at(&mut self.backend, bat_end, &self.footer.raw)?;
+                }
+            }
+        }
+
+        // Mark every entry past the in-use prefix as unallocated (including any padding).
+        let unused_start = dyn_hdr.table_offset + (self.bat.len() as u64) * 4;
+        let ff = [0xFFu8; 4096];
+        let mut off = unused_start;
+        while off < bat_end {
+            let len = (bat_end - off).min(ff.len() as u64) as usize;
+            backend_write_at(&mut self.backend, off, &ff[..len])?;
+            off += len as u64;
+        }
+
+        if max_entries > dyn_hdr.max_table_entries as u64 {
+            let mut raw_header = [0u8; 1024];
+            self.backend_read_at(
+                self.footer.data_offset,
+                &mut raw_header,
+                "vhd dynamic header truncated",
+            )?;
+            raw_header[28..32].copy_from_slice(&new_entries_u32.to_be_bytes());
+            raw_header[36..40].fill(0);
+            let checksum = vhd_checksum_dynamic_header(&raw_header);
+            raw_header[36..40].copy_from_slice(&checksum.to_be_bytes());
+            backend_write_at(&mut self.backend, self.footer.data_offset, &raw_header)?;
+            if let Some(dynamic) = self.dynamic.as_mut() {
+                dynamic.max_table_entries = new_entries_u32;
+            }
+        }
+
+        self.footer.current_size = new_capacity;
+        self.footer.raw[48..56].copy_from_slice(&new_capacity.to_be_bytes());
+        self.footer.rewrite_checksum();
+        let footer_offset = backend_len(&mut self.backend)? - SECTOR_SIZE as u64;
+        backend_write_at(&mut self.backend, footer_offset, &self.footer.raw)?;
+        backend_write_at(&mut self.backend, 0, &self.footer.raw)?;
+
+        self.bat.resize(new_entries_usize, u32::MAX);
+        Ok(())
+    }
+
+    /// Move every allocated block starting below `limit` to the end of the file.
+    fn evacuate_blocks_below(
+        &mut self,
+        limit: u64,
+        bitmap_size: u64,
+        dyn_hdr: &VhdDynamicHeader,
+    ) -> Result<()> {
+        let block_total_size = bitmap_size
+            .checked_add(dyn_hdr.block_size as u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        let mut buf = vec![0u8; (block_total_size as usize).min(64 * 1024)];
+
+        for block_index in 0..self.bat.len() {
+            let entry = self.bat[block_index];
+            if entry == u32::MAX {
+                continue;
+            }
+            let src = (entry as u64) * SECTOR_SIZE as u64;
+            if src >= limit {
+                continue;
+            }
+
+            // Same ordering as `allocate_block`: the footer moves first so it is always at EOF.
+            let file_len = backend_len(&mut self.backend)?;
+            let dst = file_len - SECTOR_SIZE as u64;
+            let new_footer_offset = dst
+                .checked_add(block_total_size)
+                .ok_or(DiskError::OffsetOverflow)?;
+            backend_set_len(&mut self.backend, new_footer_offset + SECTOR_SIZE as u64)?;
+            backend_write_at(&mut self.backend, new_footer_offset, &self.footer.raw)?;
+
+            let mut off = 0u64;
+            while off < block_total_size {
+                let len = (block_total_size - off).min(buf.len() as u64) as usize;
+                self.backend_read_at(src + off, &mut buf[..len], "vhd block truncated")?;
+                backend_write_at(&mut self.backend, dst + off, &buf[..len])?;
+                off += len as u64;
+            }
+
+            let block_sector: u32 = (dst / SECTOR_SIZE as u64)
+                .try_into()
+                .map_err(|_| DiskError::Unsupported("vhd block offset"))?;
+            let bat_entry_offset = dyn_hdr
+                .table_offset
+                .checked_add((block_index as u64) * 4)
+                .ok_or(DiskError::OffsetOverflow)?;
+            backend_write_at(
+                &mut self.backend,
+                bat_entry_offset,
+                &block_sector.to_be_bytes(),
+            )?;
+            self.bat[block_index] = block_sector;
+        }
+
+        self.bitmap_cache.clear();
+        Ok(())
+    }
 }
 
 impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for VhdDisk<B> {
@@ -1159,7 +1755,9 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for VhdDisk<B
             let bat_entry = self.bat[block_index];
             if bat_entry == u32::MAX {
                 if let Some(parent) = self.parent.as_mut() {
-                    parent.read_at(abs, &mut buf[pos..pos + chunk_len])?;
+                    parent
+                        .read_at(abs, &mut buf[pos..pos + chunk_len])
+                        .map_err(|e| e.with_context(DiskLayer::Vhd, abs, chunk_len))?;
                 } else {
                     buf[pos..pos + chunk_len].fill(0);
                 }
@@ -1207,7 +1805,9 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for VhdDisk<B
                     let abs_run = offset
                         .checked_add(pos as u64)
                         .ok_or(DiskError::OffsetOverflow)?;
-                    parent.read_at(abs_run, &mut buf[pos..pos + run_len])?;
+                    parent
+                        .read_at(abs_run, &mut buf[pos..pos + run_len])
+                        .map_err(|e| e.with_context(DiskLayer::Vhd, abs_run, run_len))?;
                 } else {
                     buf[pos..pos + run_len].fill(0);
                 }
@@ -1236,7 +1836,7 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for VhdDisk<B
                 .fixed_data_offset
                 .checked_add(offset)
                 .ok_or(DiskError::OffsetOverflow)?;
-            self.backend.write_at(phys, buf)?;
+            backend_write_at(&mut self.backend, phys, buf)?;
             return Ok(());
         }
 
@@ -1267,12 +1867,545 @@ impl<B: StorageBackend + crate::disk::VirtualDiskSend> VirtualDisk for VhdDisk<B
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.backend.flush()?;
+        self.backend
+            .flush()
+            .map_err(|e| e.in_layer(DiskLayer::Vhd))?;
         if let Some(parent) = self.parent.as_mut() {
-            parent.flush()?;
+            parent.flush().map_err(|e| e.in_layer(DiskLayer::Vhd))?;
         }
         Ok(())
     }
+
+    fn resize(&mut self, new_capacity: u64) -> Result<()> {
+        if !check_grow(self.footer.current_size, new_capacity)? {
+            return Ok(());
+        }
+        match self.footer.disk_type {
+            VHD_DISK_TYPE_DYNAMIC if self.parent.is_none() => self.grow_dynamic(new_capacity),
+            VHD_DISK_TYPE_FIXED => Err(DiskError::Unsupported("vhd fixed disk resize")),
+            _ => Err(DiskError::Unsupported("vhd differencing disk resize")),
+        }
+    }
+
+    fn allocation_status(&mut self, offset: u64, len: u64) -> Result<Vec<AllocatedRange>> {
+        checked_range(
+            offset,
+            usize::try_from(len).unwrap_or(usize::MAX),
+            self.capacity_bytes(),
+        )?;
+        let mut out = Vec::new();
+        let Some(dyn_hdr) = self.dynamic.as_ref() else {
+            push_allocated_range(&mut out, offset, len, AllocationStatus::Data);
+            return Ok(out);
+        };
+
+        let block_size = dyn_hdr.block_size as u64;
+        let (sectors_per_block, bitmap_size) = self.dyn_params()?;
+        let end = offset + len;
+        let mut cur = offset;
+        while cur < end {
+            let block_index: usize = (cur / block_size)
+                .try_into()
+                .map_err(|_| DiskError::CorruptImage("vhd block index out of range"))?;
+            let within_block = cur % block_size;
+            let chunk_len = (block_size - within_block).min(end - cur);
+
+            let bat_entry = *self
+                .bat
+                .get(block_index)
+                .ok_or(DiskError::CorruptImage("vhd block index out of range"))?;
+            if bat_entry == u32::MAX {
+                self.push_unstored_range(&mut out, cur, chunk_len)?;
+                cur += chunk_len;
+                continue;
+            }
+
+            let block_start = (bat_entry as u64)
+                .checked_mul(SECTOR_SIZE as u64)
+                .ok_or(DiskError::OffsetOverflow)?;
+            self.validate_block_bounds(block_start, bitmap_size)?;
+            let bitmap = self.load_bitmap(block_start, bitmap_size)?;
+
+            let mut within = within_block;
+            let chunk_end = cur + chunk_len;
+            while cur < chunk_end {
+                let sector_in_block = within / SECTOR_SIZE as u64;
+                if sector_in_block >= sectors_per_block {
+                    return Err(DiskError::CorruptImage("vhd sector index out of range"));
+                }
+                let allocated = Self::bitmap_get(bitmap.as_slice(), sector_in_block)?;
+                let run_len = Self::sector_run_len(
+                    bitmap.as_slice(),
+                    sectors_per_block,
+                    within,
+                    chunk_end - cur,
+                    allocated,
+                )?;
+                if allocated {
+                    push_allocated_range(&mut out, cur, run_len, AllocationStatus::Data);
+                } else {
+                    self.push_unstored_range(&mut out, cur, run_len)?;
+                }
+                within += run_len;
+                cur += run_len;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// The footer [`inspect`] based its remaining checks on.
+struct InspectedFooter {
+    location: VhdFooterLocation,
+    raw: [u8; SECTOR_SIZE],
+    /// Checksum matches.
+    intact: bool,
+    /// Fields parse (ignoring the checksum) and the disk type is supported.
+    fields_valid: bool,
+    disk_type: u32,
+}
+
+struct InspectedDynamicHeader {
+    offset: u64,
+    raw: [u8; 1024],
+    /// Checksum matches; the other fields are always valid.
+    intact: bool,
+}
+
+/// Everything [`VhdDisk::validate`] reports, plus what [`VhdDisk::repair`] needs to act on it.
+struct VhdInspection {
+    report: VhdValidationReport,
+    trailing: Option<[u8; SECTOR_SIZE]>,
+    copy: Option<[u8; SECTOR_SIZE]>,
+    /// Where the trailing footer is or, if the file was cut short, belongs: right after the data.
+    /// `None` if that cannot be determined.
+    trailing_footer_offset: Option<u64>,
+    footer: Option<InspectedFooter>,
+    dynamic_header: Option<InspectedDynamicHeader>,
+    table_offset: u64,
+    bad_bat_entries: Vec<u64>,
+}
+
+enum FooterState {
+    CookieMissing,
+    ChecksumMismatch { stored: u32, computed: u32 },
+    Intact,
+}
+
+impl FooterState {
+    fn of(raw: &[u8; SECTOR_SIZE]) -> Self {
+        if raw[..8] != VHD_FOOTER_COOKIE {
+            return FooterState::CookieMissing;
+        }
+        let stored = be_u32(&raw[64..68]);
+        let computed = vhd_checksum_footer(raw);
+        if stored == computed {
+            FooterState::Intact
+        } else {
+            FooterState::ChecksumMismatch { stored, computed }
+        }
+    }
+
+    fn problem(&self, location: VhdFooterLocation) -> Option<VhdProblem> {
+        match *self {
+            FooterState::CookieMissing => Some(VhdProblem::FooterCookieMissing { location }),
+            FooterState::ChecksumMismatch { stored, computed } => {
+                Some(VhdProblem::FooterChecksumMismatch {
+                    location,
+                    stored,
+                    computed,
+                })
+            }
+            FooterState::Intact => None,
+        }
+    }
+}
+
+fn inspect<B: StorageBackend>(backend: &mut B) -> Result<VhdInspection> {
+    let len = backend_len(backend)?;
+    let mut inspection = VhdInspection {
+        report: VhdValidationReport {
+            file_len: len,
+            ..VhdValidationReport::default()
+        },
+        trailing: None,
+        copy: None,
+        trailing_footer_offset: None,
+        footer: None,
+        dynamic_header: None,
+        table_offset: 0,
+        bad_bat_entries: Vec::new(),
+    };
+    let report = &mut inspection.report;
+
+    if len < SECTOR_SIZE as u64 || !len.is_multiple_of(SECTOR_SIZE as u64) {
+        report.problems.push(VhdProblem::FileLengthInvalid { len });
+        if len < SECTOR_SIZE as u64 {
+            return Ok(inspection);
+        }
+    }
+    let footer_offset = len - SECTOR_SIZE as u64;
+
+    let mut trailing = [0u8; SECTOR_SIZE];
+    backend
+        .read_at(footer_offset, &mut trailing)
+        .map_err(|e| e.with_context(DiskLayer::Vhd, footer_offset, SECTOR_SIZE))?;
+    let copy = if len >= 2 * SECTOR_SIZE as u64 {
+        let mut copy = [0u8; SECTOR_SIZE];
+        backend
+            .read_at(0, &mut copy)
+            .map_err(|e| e.with_context(DiskLayer::Vhd, 0, SECTOR_SIZE))?;
+        Some(copy)
+    } else {
+        None
+    };
+    inspection.trailing = Some(trailing);
+    inspection.copy = copy;
+
+    let trailing_state = FooterState::of(&trailing);
+    let copy_state = copy.as_ref().map(FooterState::of);
+    report
+        .problems
+        .extend(trailing_state.problem(VhdFooterLocation::Trailing));
+
+    // Without a trailing footer in the last whole sector the file was cut short (or its end
+    // overwritten): metadata and blocks may then run up to the end of the file, and the footer
+    // is restored after them.
+    let trailing_present = len.is_multiple_of(SECTOR_SIZE as u64)
+        && !matches!(trailing_state, FooterState::CookieMissing);
+    let data_limit = if trailing_present {
+        inspection.trailing_footer_offset = Some(footer_offset);
+        footer_offset
+    } else {
+        len - len % SECTOR_SIZE as u64
+    };
+
+    // Prefer an intact footer, trailing first; fall back to one with just a bad checksum.
+    let candidates = [
+        (
+            VhdFooterLocation::Trailing,
+            Some(trailing),
+            trailing_present.then_some(&trailing_state),
+        ),
+        (VhdFooterLocation::Copy, copy, copy_state.as_ref()),
+    ];
+    let chosen = candidates
+        .iter()
+        .find(|(_, _, state)| matches!(state, Some(FooterState::Intact)))
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|(_, _, state)| matches!(state, Some(FooterState::ChecksumMismatch { .. })))
+        });
+    let Some(&(location, Some(raw), Some(state))) = chosen else {
+        // Nothing that looks like a footer; a copy with only a bad checksum would have been
+        // chosen, so there is nothing more to say about it either.
+        return Ok(inspection);
+    };
+    let intact = matches!(state, FooterState::Intact);
+
+    let parsed = VhdFooter::parse(with_footer_checksum(raw));
+    let disk_type = be_u32(&raw[60..64]);
+    let current_size = be_u64(&raw[48..56]);
+    let sparse = matches!(
+        disk_type,
+        VHD_DISK_TYPE_DYNAMIC | VHD_DISK_TYPE_DIFFERENCING
+    );
+
+    // The copy is only required (and required to match) on dynamic and differencing disks; a
+    // fixed disk's first sector is normally guest data.
+    let copy_problem = match &copy_state {
+        Some(state @ FooterState::ChecksumMismatch { .. }) => {
+            state.problem(VhdFooterLocation::Copy)
+        }
+        Some(FooterState::Intact) if sparse && copy != Some(raw) => {
+            Some(VhdProblem::FooterCopyMismatch)
+        }
+        Some(FooterState::CookieMissing) | None if sparse => {
+            Some(VhdProblem::FooterCookieMissing {
+                location: VhdFooterLocation::Copy,
+            })
+        }
+        _ => None,
+    };
+    report.problems.extend(copy_problem);
+
+    let mut footer = InspectedFooter {
+        location,
+        raw,
+        intact,
+        fields_valid: false,
+        disk_type,
+    };
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(DiskError::CorruptImage(reason) | DiskError::Unsupported(reason)) => {
+            report
+                .problems
+                .push(VhdProblem::FooterInvalid { location, reason });
+            inspection.footer = Some(footer);
+            return Ok(inspection);
+        }
+        Err(e) => return Err(e),
+    };
+    if !matches!(
+        disk_type,
+        VHD_DISK_TYPE_FIXED | VHD_DISK_TYPE_DYNAMIC | VHD_DISK_TYPE_DIFFERENCING
+    ) {
+        report
+            .problems
+            .push(VhdProblem::DiskTypeUnsupported { disk_type });
+        inspection.footer = Some(footer);
+        return Ok(inspection);
+    }
+    footer.fields_valid = true;
+    inspection.footer = Some(footer);
+    report.disk_type = Some(disk_type);
+    report.current_size = Some(current_size);
+
+    // CHS geometry is informational, but it must not address past the end of the disk. All-zero
+    // geometry means the creator did not record one.
+    let cylinders = u16::from_be_bytes([raw[56], raw[57]]);
+    let heads = raw[58];
+    let sectors_per_track = raw[59];
+    let geometry_sectors = u64::from(cylinders) * u64::from(heads) * u64::from(sectors_per_track);
+    if geometry_sectors * SECTOR_SIZE as u64 > current_size {
+        report.problems.push(VhdProblem::GeometryMismatch {
+            cylinders,
+            heads,
+            sectors_per_track,
+            current_size,
+        });
+    }
+
+    if !sparse {
+        // A fixed footer copy at offset 0 with the same size moves the data after it (see
+        // `VhdDisk::open`), so the file must then hold the copy as well.
+        let has_copy = copy.is_some_and(|copy| {
+            VhdFooter::parse(copy)
+                .is_ok_and(|c| c.disk_type == VHD_DISK_TYPE_FIXED && c.current_size == current_size)
+        });
+        let data_start = if has_copy { SECTOR_SIZE as u64 } else { 0 };
+        let data_end = current_size
+            .checked_add(data_start)
+            .ok_or(DiskError::OffsetOverflow)?;
+        let required_len = data_end
+            .checked_add(SECTOR_SIZE as u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        if len < required_len {
+            report
+                .problems
+                .push(VhdProblem::FixedDiskTruncated { len, required_len });
+        }
+        // The footer always follows the data; with data missing there is nowhere to put it.
+        inspection.trailing_footer_offset = (len >= data_end).then_some(data_end);
+        return Ok(inspection);
+    }
+
+    let header_offset = parsed.data_offset;
+    let header_end = header_offset.checked_add(1024);
+    if !header_offset.is_multiple_of(SECTOR_SIZE as u64)
+        || header_offset < SECTOR_SIZE as u64
+        || header_end.is_none_or(|end| end > data_limit)
+    {
+        report.problems.push(VhdProblem::DynamicHeaderOutOfBounds {
+            offset: header_offset,
+        });
+        return Ok(inspection);
+    }
+    let header_end = header_offset + 1024;
+
+    let mut raw_header = [0u8; 1024];
+    backend
+        .read_at(header_offset, &mut raw_header)
+        .map_err(|e| e.with_context(DiskLayer::Vhd, header_offset, raw_header.len()))?;
+    if raw_header[..8] != VHD_DYNAMIC_COOKIE {
+        report
+            .problems
+            .push(VhdProblem::DynamicHeaderCookieMissing {
+                offset: header_offset,
+            });
+        return Ok(inspection);
+    }
+    let stored = be_u32(&raw_header[36..40]);
+    let computed = vhd_checksum_dynamic_header(&raw_header);
+    if stored != computed {
+        report
+            .problems
+            .push(VhdProblem::DynamicHeaderChecksumMismatch { stored, computed });
+    }
+    let dynamic = match VhdDynamicHeader::parse(&with_dynamic_header_checksum(raw_header)) {
+        Ok(dynamic) => dynamic,
+        Err(DiskError::CorruptImage(reason) | DiskError::Unsupported(reason)) => {
+            report
+                .problems
+                .push(VhdProblem::DynamicHeaderInvalid { reason });
+            return Ok(inspection);
+        }
+        Err(e) => return Err(e),
+    };
+    inspection.dynamic_header = Some(InspectedDynamicHeader {
+        offset: header_offset,
+        raw: raw_header,
+        intact: stored == computed,
+    });
+
+    let required_entries = current_size.div_ceil(u64::from(dynamic.block_size));
+    if u64::from(dynamic.max_table_entries) < required_entries {
+        report.problems.push(VhdProblem::BatTooSmall {
+            max_table_entries: dynamic.max_table_entries,
+            required_entries,
+        });
+        return Ok(inspection);
+    }
+    let table_bytes = align_up_u64(u64::from(dynamic.max_table_entries) * 4, SECTOR_SIZE as u64)?;
+    if table_bytes > MAX_BAT_BYTES {
+        return Err(DiskError::Unsupported("vhd bat too large"));
+    }
+    let table_offset = dynamic.table_offset;
+    let table_end = table_offset.checked_add(table_bytes);
+    if table_end.is_none_or(|end| end > data_limit)
+        || table_offset < SECTOR_SIZE as u64
+        || (table_offset < header_end && table_end.is_some_and(|end| header_offset < end))
+    {
+        report.problems.push(VhdProblem::BatOutOfBounds {
+            table_offset,
+            table_bytes,
+            file_len: len,
+        });
+        return Ok(inspection);
+    }
+    inspection.table_offset = table_offset;
+    let table_end = table_offset + table_bytes;
+
+    let bat = read_bat(backend, table_offset, required_entries)?;
+    let sectors_per_block = u64::from(dynamic.block_size) / SECTOR_SIZE as u64;
+    let bitmap_size = align_up_u64(sectors_per_block.div_ceil(8), SECTOR_SIZE as u64)?;
+    let block_total_size = bitmap_size + u64::from(dynamic.block_size);
+    let block_total_sectors = block_total_size / SECTOR_SIZE as u64;
+    let data_region_start = header_end.max(table_end);
+
+    let mut reported = 0;
+    let mut allocated: Vec<(u32, u64)> = Vec::new();
+    for (index, &entry) in bat.iter().enumerate() {
+        if entry == u32::MAX {
+            continue;
+        }
+        let index = index as u64;
+        let block_start = u64::from(entry) * SECTOR_SIZE as u64;
+        if block_start < data_region_start || block_start + block_total_size > data_limit {
+            inspection.bad_bat_entries.push(index);
+            report.bat_entries_out_of_range += 1;
+            report.push_bat_problem(
+                &mut reported,
+                VhdProblem::BatEntryOutOfRange {
+                    index,
+                    sector: entry,
+                },
+            );
+            continue;
+        }
+        allocated.push((entry, index));
+    }
+    allocated.sort_unstable();
+    for w in allocated.windows(2) {
+        let ((prev_start, first), (next_start, second)) = (w[0], w[1]);
+        if u64::from(next_start) < u64::from(prev_start) + block_total_sectors {
+            report.push_bat_problem(&mut reported, VhdProblem::BlocksOverlap { first, second });
+        }
+    }
+    if !trailing_present {
+        let data_end = allocated.last().map_or(data_region_start, |&(start, _)| {
+            u64::from(start) * SECTOR_SIZE as u64 + block_total_size
+        });
+        inspection.trailing_footer_offset = Some(align_up_u64(
+            data_end.max(data_region_start),
+            SECTOR_SIZE as u64,
+        )?);
+    }
+
+    Ok(inspection)
+}
+
+fn with_footer_checksum(mut raw: [u8; SECTOR_SIZE]) -> [u8; SECTOR_SIZE] {
+    let checksum = vhd_checksum_footer(&raw);
+    raw[64..68].copy_from_slice(&checksum.to_be_bytes());
+    raw
+}
+
+fn with_dynamic_header_checksum(mut raw: [u8; 1024]) -> [u8; 1024] {
+    let checksum = vhd_checksum_dynamic_header(&raw);
+    raw[36..40].copy_from_slice(&checksum.to_be_bytes());
+    raw
+}
+
+fn decode_locator_path(platform_code: &[u8; 4], data: &[u8]) -> String {
+    match platform_code {
+        // Windows locators are UTF-16LE.
+        b"W2ru" | b"W2ku" => {
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&u| u != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => {
+            let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+            String::from_utf8_lossy(&data[..end]).into_owned()
+        }
+    }
+}
+
+/// Read the first `entries` BAT entries at `table_offset`.
+///
+/// Reads in chunks so large tables don't need an additional full-size temporary buffer.
+fn read_bat<B: StorageBackend>(
+    backend: &mut B,
+    table_offset: u64,
+    entries: u64,
+) -> Result<Vec<u32>> {
+    let bat_bytes = entries.checked_mul(4).ok_or(DiskError::OffsetOverflow)?;
+    if bat_bytes > MAX_BAT_BYTES {
+        return Err(DiskError::Unsupported("vhd bat too large"));
+    }
+    let entries: usize = entries
+        .try_into()
+        .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+    let bat_bytes_usize: usize = bat_bytes
+        .try_into()
+        .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+
+    let mut bat = Vec::new();
+    bat.try_reserve_exact(entries)
+        .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+
+    let mut buf = Vec::new();
+    let buf_len = bat_bytes_usize.min(VHD_TABLE_READ_CHUNK_BYTES);
+    buf.try_reserve_exact(buf_len)
+        .map_err(|_| DiskError::Unsupported("vhd bat too large"))?;
+    buf.resize(buf_len, 0);
+
+    let mut remaining = bat_bytes_usize;
+    let mut off = table_offset;
+    while remaining > 0 {
+        let read_len = remaining.min(buf.len());
+        match backend.read_at(off, &mut buf[..read_len]) {
+            Ok(()) => {}
+            Err(DiskError::OutOfBounds { .. }) => {
+                return Err(DiskError::CorruptImage("vhd bat truncated"));
+            }
+            Err(e) => return Err(e),
+        }
+        for chunk in buf[..read_len].chunks_exact(4) {
+            bat.push(be_u32(chunk));
+        }
+        off = off
+            .checked_add(read_len as u64)
+            .ok_or(DiskError::OffsetOverflow)?;
+        remaining -= read_len;
+    }
+    Ok(bat)
 }
 
 fn be_u32(bytes: &[u8]) -> u32 {
@@ -1309,13 +2442,27 @@ fn vhd_checksum_dynamic_header(raw: &[u8; 1024]) -> u32 {
     !sum
 }
 
+fn backend_write_at<B: StorageBackend>(backend: &mut B, offset: u64, buf: &[u8]) -> Result<()> {
+    backend
+        .write_at(offset, buf)
+        .map_err(|e| e.with_context(DiskLayer::Vhd, offset, buf.len()))
+}
+
+fn backend_len<B: StorageBackend>(backend: &mut B) -> Result<u64> {
+    backend.len().map_err(|e| e.in_layer(DiskLayer::Vhd))
+}
+
+fn backend_set_len<B: StorageBackend>(backend: &mut B, len: u64) -> Result<()> {
+    backend.set_len(len).map_err(|e| e.in_layer(DiskLayer::Vhd))
+}
+
 fn write_zeroes<B: StorageBackend>(backend: &mut B, mut offset: u64, mut len: u64) -> Result<()> {
     const CHUNK: usize = 64 * 1024;
     let buf = [0u8; CHUNK];
     while len > 0 {
         // Convert to usize *after* clamping so we never truncate `len` on 32-bit builds.
         let to_write = len.min(CHUNK as u64) as usize;
-        backend.write_at(offset, &buf[..to_write])?;
+        backend_write_at(backend, offset, &buf[..to_write])?;
         offset = offset
             .checked_add(to_write as u64)
             .ok_or(DiskError::OffsetOverflow)?;
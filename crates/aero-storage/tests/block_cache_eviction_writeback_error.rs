@@ -85,7 +85,7 @@ fn block_cache_does_not_lose_dirty_block_on_eviction_writeback_error() {
     // Access block 1 to force eviction of block 0. The write-back should fail.
     let mut tmp = [0u8; 1];
     let err = disk.read_at(block_size as u64, &mut tmp).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // The dirty data for block 0 must still be readable from the cache after the failure.
     let mut readback = [0u8; 4];
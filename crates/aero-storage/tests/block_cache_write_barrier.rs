@@ -0,0 +1,157 @@
+use aero_storage::{BlockCachedDisk, MemBackend, RawDisk, Result, VirtualDisk};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Write(u64),
+    Barrier,
+    Flush,
+}
+
+/// Disk with ordered writes (a no-op write barrier) that logs every operation it receives.
+struct TracingDisk {
+    inner: RawDisk<MemBackend>,
+    ops: Vec<Op>,
+}
+
+impl TracingDisk {
+    fn new(capacity: u64) -> Self {
+        Self {
+            inner: RawDisk::create(MemBackend::new(), capacity).unwrap(),
+            ops: Vec::new(),
+        }
+    }
+
+    fn flushes(&self) -> usize {
+        self.ops.iter().filter(|op| **op == Op::Flush).count()
+    }
+}
+
+impl VirtualDisk for TracingDisk {
+    fn capacity_bytes(&self) -> u64 {
+        self.inner.capacity_bytes()
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        self.ops.push(Op::Write(offset));
+        self.inner.write_at(offset, buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.ops.push(Op::Flush);
+        self.inner.flush()
+    }
+
+    fn write_barrier(&mut self) -> Result<()> {
+        self.ops.push(Op::Barrier);
+        Ok(())
+    }
+}
+
+const BLOCK: usize = 16;
+
+fn cached(blocks: usize) -> BlockCachedDisk<TracingDisk> {
+    BlockCachedDisk::new(TracingDisk::new(8 * BLOCK as u64), BLOCK, blocks).unwrap()
+}
+
+#[test]
+fn write_barrier_write_barrier_pattern_costs_one_backend_flush() {
+    let mut disk = cached(8);
+
+    // Guest: write A, FLUSH, write B, FLUSH; the controller holds both FLUSH completions and
+    // issues a single flush at the end of its batch.
+    disk.write_at(0, &[0xAA; BLOCK]).unwrap();
+    disk.write_barrier().unwrap();
+    disk.write_at(BLOCK as u64, &[0xBB; BLOCK]).unwrap();
+    assert!(disk.inner().ops.is_empty(), "barriers must be deferred");
+    // Only one barrier is kept pending: the second one pushes the first group out, ordered but
+    // without flushing.
+    disk.write_barrier().unwrap();
+    assert_eq!(disk.inner().ops, [Op::Write(0), Op::Barrier]);
+    disk.flush().unwrap();
+
+    assert_eq!(
+        disk.inner().ops,
+        [
+            Op::Write(0),
+            Op::Barrier,
+            Op::Write(BLOCK as u64),
+            Op::Flush
+        ]
+    );
+    assert_eq!(disk.stats().inner_flushes, 1);
+
+    // Nothing new reached the backend: repeated flushes are free.
+    disk.flush().unwrap();
+    assert_eq!(disk.inner().flushes(), 1);
+}
+
+#[test]
+fn write_after_barrier_to_same_block_propagates_the_barrier_first() {
+    let mut disk = cached(8);
+
+    disk.write_at(0, &[1; 4]).unwrap();
+    disk.write_barrier().unwrap();
+    disk.write_at(4, &[2; 4]).unwrap();
+    // The pre-barrier block contents reached the backend, ordered ahead of the newer data.
+    assert_eq!(disk.inner().ops, [Op::Write(0), Op::Barrier]);
+
+    disk.flush().unwrap();
+    assert_eq!(
+        disk.inner().ops,
+        [Op::Write(0), Op::Barrier, Op::Write(0), Op::Flush]
+    );
+
+    let mut buf = [0u8; 8];
+    disk.inner_mut().read_at(0, &mut buf).unwrap();
+    assert_eq!(buf, [1, 1, 1, 1, 2, 2, 2, 2]);
+}
+
+#[test]
+fn eviction_after_barrier_keeps_pre_barrier_data_first() {
+    let mut disk = cached(1);
+
+    disk.write_at(0, &[0xAA; BLOCK]).unwrap();
+    disk.write_barrier().unwrap();
+    // Caching block 1 evicts block 0, which belongs to the pre-barrier group.
+    disk.write_at(BLOCK as u64, &[0xBB; BLOCK]).unwrap();
+    // Caching block 2 evicts post-barrier block 1; the barrier has to reach the backend first.
+    disk.write_at(2 * BLOCK as u64, &[0xCC; BLOCK]).unwrap();
+
+    assert_eq!(
+        disk.inner().ops,
+        [Op::Write(0), Op::Barrier, Op::Write(BLOCK as u64)]
+    );
+}
+
+#[test]
+fn barriers_escalate_to_a_flush_once_the_batch_window_is_full() {
+    let mut disk = cached(8);
+    disk.set_flush_batch_window(2);
+
+    for i in 0..3u64 {
+        disk.write_at(i * BLOCK as u64, &[i as u8; BLOCK]).unwrap();
+        disk.write_barrier().unwrap();
+    }
+    assert_eq!(disk.inner().flushes(), 1);
+    assert_eq!(
+        disk.inner().ops,
+        [
+            Op::Write(0),
+            Op::Barrier,
+            Op::Write(BLOCK as u64),
+            Op::Barrier,
+            Op::Write(2 * BLOCK as u64),
+            Op::Flush
+        ]
+    );
+
+    // Window 0: every barrier is a full flush.
+    disk.set_flush_batch_window(0);
+    disk.write_at(0, &[9; BLOCK]).unwrap();
+    disk.write_barrier().unwrap();
+    assert_eq!(disk.inner().flushes(), 2);
+}
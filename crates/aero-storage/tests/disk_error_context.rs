@@ -0,0 +1,192 @@
+use std::error::Error as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use aero_storage::{
+    AeroCowDisk, AeroSparseConfig, AeroSparseDisk, BlockCachedDisk, DiskError, DiskErrorContext,
+    DiskLayer, MemBackend, RawDisk, StorageBackend, VirtualDisk,
+};
+
+const BLOCK: usize = 4096;
+
+/// Fails every read that reaches past `fail_reads_from`, remembering the last failed range.
+struct FailingBackend {
+    inner: MemBackend,
+    fail_reads_from: Arc<AtomicU64>,
+    last_failure: Arc<Mutex<Option<(u64, usize)>>>,
+}
+
+impl FailingBackend {
+    fn new(len: u64) -> Self {
+        Self {
+            inner: MemBackend::with_len(len).unwrap(),
+            fail_reads_from: Arc::new(AtomicU64::new(u64::MAX)),
+            last_failure: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl StorageBackend for FailingBackend {
+    fn len(&mut self) -> aero_storage::Result<u64> {
+        self.inner.len()
+    }
+
+    fn set_len(&mut self, len: u64) -> aero_storage::Result<()> {
+        self.inner.set_len(len)
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> aero_storage::Result<()> {
+        if offset + buf.len() as u64 > self.fail_reads_from.load(Ordering::SeqCst) {
+            *self.last_failure.lock().unwrap() = Some((offset, buf.len()));
+            return Err(DiskError::Io("injected read failure".to_string()));
+        }
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> aero_storage::Result<()> {
+        self.inner.write_at(offset, buf)
+    }
+
+    fn flush(&mut self) -> aero_storage::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn layers(err: &DiskError) -> Vec<DiskLayer> {
+    err.contexts().map(|context| context.layer).collect()
+}
+
+#[test]
+fn backend_failure_under_cache_and_cow_reports_every_layer() {
+    let backend = FailingBackend::new(16 * BLOCK as u64);
+    backend
+        .fail_reads_from
+        .store(2 * BLOCK as u64, Ordering::SeqCst);
+    let base = RawDisk::open(backend).unwrap();
+    let cow = AeroCowDisk::create(base, MemBackend::new(), BLOCK as u32).unwrap();
+    let mut disk = BlockCachedDisk::new(cow, BLOCK, 4).unwrap();
+
+    // Block 0 is readable; block 2 misses the cache and falls through to the base.
+    let mut buf = [0u8; 16];
+    disk.read_at(100, &mut buf).unwrap();
+    let err = disk.read_at(2 * BLOCK as u64 + 100, &mut buf).unwrap_err();
+
+    assert_eq!(
+        layers(&err),
+        [DiskLayer::BlockCache, DiskLayer::AeroCow, DiskLayer::Raw]
+    );
+    for context in err.contexts() {
+        assert_eq!(
+            *context,
+            DiskErrorContext::at(context.layer, 2 * BLOCK as u64, BLOCK)
+        );
+    }
+    assert_eq!(
+        err.root(),
+        &DiskError::Io("injected read failure".to_string())
+    );
+    assert_eq!(
+        err.to_string(),
+        "block-cache[offset=8192 len=4096] > cow[offset=8192 len=4096] > \
+         raw[offset=8192 len=4096]: io error: injected read failure"
+    );
+
+    // The std source chain walks the same path down to the backend's error.
+    let mut depth = 0;
+    let mut source = err.source();
+    while let Some(next) = source {
+        depth += 1;
+        source = next.source();
+    }
+    assert_eq!(depth, 3);
+}
+
+#[test]
+fn sparse_format_reports_the_image_offset_it_was_reading() {
+    let backend = FailingBackend::new(0);
+    let fail_reads_from = backend.fail_reads_from.clone();
+    let last_failure = backend.last_failure.clone();
+    let mut sparse = AeroSparseDisk::create(
+        backend,
+        AeroSparseConfig {
+            disk_size_bytes: 16 * BLOCK as u64,
+            block_size_bytes: BLOCK as u32,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    sparse.write_at(5 * BLOCK as u64, &[0xAB; BLOCK]).unwrap();
+
+    fail_reads_from.store(0, Ordering::SeqCst);
+    let mut disk = BlockCachedDisk::new(sparse, BLOCK, 2).unwrap();
+    let mut buf = [0u8; 8];
+    let err = disk.read_at(5 * BLOCK as u64 + 8, &mut buf).unwrap_err();
+
+    let (phys, len) = last_failure.lock().unwrap().unwrap();
+    assert_eq!(len, BLOCK);
+    assert_eq!(
+        err.contexts().copied().collect::<Vec<_>>(),
+        [
+            DiskErrorContext::at(DiskLayer::BlockCache, 5 * BLOCK as u64, BLOCK),
+            DiskErrorContext::at(DiskLayer::AeroSparse, phys, BLOCK),
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "block-cache[offset=20480 len=4096] > aerosparse[offset={phys} len=4096]: \
+             io error: injected read failure"
+        )
+    );
+}
+
+#[test]
+fn errors_raised_by_a_layer_itself_are_not_wrapped() {
+    let mut disk = BlockCachedDisk::new(
+        RawDisk::create(MemBackend::new(), 4 * BLOCK as u64).unwrap(),
+        BLOCK,
+        2,
+    )
+    .unwrap();
+    let mut buf = [0u8; 8];
+    let err = disk.read_at(4 * BLOCK as u64, &mut buf).unwrap_err();
+    assert!(matches!(err, DiskError::OutOfBounds { .. }));
+    assert_eq!(err.contexts().count(), 0);
+    assert_eq!(err.root(), &err);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn native_io_errors_keep_the_std_error_as_source() {
+    use std::io::Write as _;
+
+    let mut tmp = tempfile::NamedTempFile::new().unwrap();
+    tmp.write_all(&[0u8; BLOCK]).unwrap();
+    tmp.flush().unwrap();
+
+    // Opened without write access, so the write fails in the OS rather than the backend.
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .open(tmp.path())
+        .unwrap();
+    let backend = aero_storage::StdFileBackend::from_file_with_path(file, tmp.path());
+    let mut disk = RawDisk::open(backend).unwrap();
+
+    let err = disk.write_at(512, &[1; 512]).unwrap_err();
+    assert_eq!(layers(&err), [DiskLayer::Raw]);
+    assert!(matches!(err.root(), DiskError::NativeIo { .. }));
+
+    let io = err.io_error().expect("root should be a native io error");
+    let std_source = err
+        .root()
+        .source()
+        .and_then(|source| source.downcast_ref::<aero_storage::IoSource>())
+        .expect("NativeIo exposes its io error as the source");
+    assert_eq!(std_source.kind(), io.kind());
+    assert!(err
+        .to_string()
+        .starts_with("raw[offset=512 len=512]: io error: write_at failed"));
+
+    // Clones compare equal and share the underlying io error.
+    assert_eq!(err.clone(), err);
+}
@@ -0,0 +1,228 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_storage::{
+    AeroCowDisk, AeroSparseConfig, AeroSparseDisk, DiskError, MemBackend, RawDisk, StorageBackend,
+    VhdDisk, VirtualDisk, SECTOR_SIZE,
+};
+
+const SECTOR: usize = SECTOR_SIZE;
+
+fn write_be_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_be_bytes());
+}
+
+fn write_be_u64(buf: &mut [u8], offset: usize, val: u64) {
+    buf[offset..offset + 8].copy_from_slice(&val.to_be_bytes());
+}
+
+fn vhd_checksum(raw: &[u8], skip: std::ops::Range<usize>) -> u32 {
+    let mut sum: u32 = 0;
+    for (i, b) in raw.iter().enumerate() {
+        if skip.contains(&i) {
+            continue;
+        }
+        sum = sum.wrapping_add(*b as u32);
+    }
+    !sum
+}
+
+fn make_vhd_dynamic_empty(virtual_size: u64, block_size: u32) -> MemBackend {
+    let dyn_header_offset = SECTOR as u64;
+    let table_offset = dyn_header_offset + 1024;
+    let max_table_entries = virtual_size.div_ceil(block_size as u64) as u32;
+    let bat_size = (max_table_entries as u64 * 4).div_ceil(SECTOR as u64) * (SECTOR as u64);
+
+    let mut footer = [0u8; SECTOR];
+    footer[0..8].copy_from_slice(b"conectix");
+    write_be_u32(&mut footer, 8, 2);
+    write_be_u32(&mut footer, 12, 0x0001_0000);
+    write_be_u64(&mut footer, 16, dyn_header_offset);
+    write_be_u64(&mut footer, 40, virtual_size);
+    write_be_u64(&mut footer, 48, virtual_size);
+    write_be_u32(&mut footer, 60, 3);
+    let checksum = vhd_checksum(&footer, 64..68);
+    write_be_u32(&mut footer, 64, checksum);
+
+    let file_len = (SECTOR as u64) + 1024 + bat_size + (SECTOR as u64);
+    let mut storage = MemBackend::with_len(file_len).unwrap();
+    storage.write_at(0, &footer).unwrap();
+    storage
+        .write_at(file_len - (SECTOR as u64), &footer)
+        .unwrap();
+
+    let mut dyn_header = [0u8; 1024];
+    dyn_header[0..8].copy_from_slice(b"cxsparse");
+    write_be_u64(&mut dyn_header, 8, u64::MAX);
+    write_be_u64(&mut dyn_header, 16, table_offset);
+    write_be_u32(&mut dyn_header, 24, 0x0001_0000);
+    write_be_u32(&mut dyn_header, 28, max_table_entries);
+    write_be_u32(&mut dyn_header, 32, block_size);
+    let checksum = vhd_checksum(&dyn_header, 36..40);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    storage.write_at(dyn_header_offset, &dyn_header).unwrap();
+
+    storage
+        .write_at(table_offset, &vec![0xFFu8; bat_size as usize])
+        .unwrap();
+    storage
+}
+
+fn assert_bytes(disk: &mut dyn VirtualDisk, offset: u64, expected: &[u8]) {
+    let mut buf = vec![0u8; expected.len()];
+    disk.read_at(offset, &mut buf).unwrap();
+    assert_eq!(buf, expected, "mismatch at offset {offset}");
+}
+
+#[test]
+fn default_resize_is_unsupported() {
+    let mut disk =
+        aero_storage::ReadOnlyDisk::new(RawDisk::create(MemBackend::new(), 4096).unwrap());
+    assert!(matches!(disk.resize(8192), Err(DiskError::Unsupported(_))));
+}
+
+#[test]
+fn raw_grow_zero_fills_and_rejects_shrink() {
+    let mut disk = RawDisk::create(MemBackend::new(), 4096).unwrap();
+    disk.write_at(0, &[0xAA; 4096]).unwrap();
+
+    disk.resize(16 * 1024).unwrap();
+    assert_eq!(disk.capacity_bytes(), 16 * 1024);
+    assert_bytes(&mut disk, 0, &[0xAA; 4096]);
+    assert_bytes(&mut disk, 4096, &[0; 12 * 1024]);
+    disk.write_at(12 * 1024, &[0x55; 4096]).unwrap();
+
+    assert!(matches!(disk.resize(8192), Err(DiskError::Unsupported(_))));
+    assert!(matches!(
+        disk.resize(16 * 1024 + 1),
+        Err(DiskError::InvalidConfig(_))
+    ));
+    // Same size is a no-op.
+    disk.resize(16 * 1024).unwrap();
+
+    let mut reopened = RawDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(reopened.capacity_bytes(), 16 * 1024);
+    assert_bytes(&mut reopened, 12 * 1024, &[0x55; 4096]);
+}
+
+#[test]
+fn aerosparse_grow_within_existing_table_block() {
+    let mut disk = AeroSparseDisk::create(
+        MemBackend::new(),
+        AeroSparseConfig {
+            disk_size_bytes: 8 * 4096 + 512,
+            block_size_bytes: 4096,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    disk.write_at(8 * 4096, &[0x11; 512]).unwrap();
+
+    disk.resize(16 * 4096).unwrap();
+    assert_eq!(disk.header().table_entries, 16);
+    assert_eq!(disk.header().data_offset, 4096);
+    assert_bytes(&mut disk, 8 * 4096, &[0x11; 512]);
+    assert_bytes(&mut disk, 8 * 4096 + 512, &[0; 4096 - 512]);
+    assert_bytes(&mut disk, 9 * 4096, &[0; 7 * 4096]);
+
+    let mut reopened = AeroSparseDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(reopened.capacity_bytes(), 16 * 4096);
+    assert_bytes(&mut reopened, 8 * 4096, &[0x11; 512]);
+}
+
+#[test]
+fn aerosparse_grow_relocates_blocks_under_the_new_table() {
+    // 500 entries fit in the first 4 KiB block; 600 entries push the data region to 8 KiB, so the
+    // first allocated data block must move.
+    let mut disk = AeroSparseDisk::create(
+        MemBackend::new(),
+        AeroSparseConfig {
+            disk_size_bytes: 500 * 4096,
+            block_size_bytes: 4096,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(disk.header().data_offset, 4096);
+    for (i, block) in [0u64, 10, 499].into_iter().enumerate() {
+        disk.write_at(block * 4096, &[i as u8 + 1; 4096]).unwrap();
+    }
+
+    disk.resize(600 * 4096).unwrap();
+    assert_eq!(disk.header().data_offset, 8192);
+    assert_eq!(disk.allocated_block_count(), 3);
+    for (i, block) in [0u64, 10, 499].into_iter().enumerate() {
+        assert_bytes(&mut disk, block * 4096, &[i as u8 + 1; 4096]);
+    }
+    assert_bytes(&mut disk, 500 * 4096, &[0; 4096]);
+    disk.write_at(599 * 4096, &[0x77; 4096]).unwrap();
+
+    let mut reopened = AeroSparseDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(reopened.capacity_bytes(), 600 * 4096);
+    assert_eq!(reopened.allocated_block_count(), 4);
+    for (i, block) in [0u64, 10, 499].into_iter().enumerate() {
+        assert_bytes(&mut reopened, block * 4096, &[i as u8 + 1; 4096]);
+    }
+    assert_bytes(&mut reopened, 599 * 4096, &[0x77; 4096]);
+    assert_bytes(&mut reopened, 550 * 4096, &[0; 4096]);
+}
+
+#[test]
+fn cow_grow_covers_range_past_base() {
+    let mut base = RawDisk::create(MemBackend::new(), 64 * 1024).unwrap();
+    base.write_at(0, &[0xB0; 64 * 1024]).unwrap();
+
+    let mut disk = AeroCowDisk::create(base, MemBackend::new(), 4096).unwrap();
+    disk.write_at(4096, &[0xC1; 4096]).unwrap();
+
+    disk.resize(128 * 1024).unwrap();
+    assert_eq!(disk.capacity_bytes(), 128 * 1024);
+    assert_bytes(&mut disk, 0, &[0xB0; 4096]);
+    assert_bytes(&mut disk, 4096, &[0xC1; 4096]);
+    assert_bytes(&mut disk, 64 * 1024, &[0; 64 * 1024]);
+
+    // A partial write past the base seeds the rest of the block with zeros, not base data.
+    disk.write_at(80 * 1024 + 512, &[0xC2; 512]).unwrap();
+    assert_bytes(&mut disk, 80 * 1024, &[0; 512]);
+    assert_bytes(&mut disk, 80 * 1024 + 512, &[0xC2; 512]);
+    assert_bytes(&mut disk, 80 * 1024 + 1024, &[0; 3072]);
+
+    let (base, overlay) = disk.into_parts();
+    let mut reopened = AeroCowDisk::open(base, overlay.into_backend()).unwrap();
+    assert_eq!(reopened.capacity_bytes(), 128 * 1024);
+    assert_bytes(&mut reopened, 60 * 1024, &[0xB0; 4096]);
+    assert_bytes(&mut reopened, 80 * 1024 + 512, &[0xC2; 512]);
+}
+
+#[test]
+fn vhd_dynamic_grow_within_bat_padding_and_with_block_relocation() {
+    // 16 BAT entries occupy one padded sector, which has room for 128.
+    let mut disk = VhdDisk::open(make_vhd_dynamic_empty(64 * 1024, 4096)).unwrap();
+    disk.write_at(0, &[0x31; 4096]).unwrap();
+    disk.write_at(60 * 1024, &[0x32; 4096]).unwrap();
+
+    disk.resize(256 * 1024).unwrap();
+    assert_eq!(disk.capacity_bytes(), 256 * 1024);
+    assert_bytes(&mut disk, 64 * 1024, &[0; 4096]);
+    disk.write_at(252 * 1024, &[0x33; 4096]).unwrap();
+
+    // 256 entries need a second BAT sector, which is where the first data block lives.
+    disk.resize(1024 * 1024).unwrap();
+    assert_bytes(&mut disk, 0, &[0x31; 4096]);
+    assert_bytes(&mut disk, 60 * 1024, &[0x32; 4096]);
+    assert_bytes(&mut disk, 252 * 1024, &[0x33; 4096]);
+    assert_bytes(&mut disk, 512 * 1024, &[0; 4096]);
+    disk.write_at(1020 * 1024, &[0x34; 4096]).unwrap();
+
+    let mut reopened = VhdDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(reopened.capacity_bytes(), 1024 * 1024);
+    assert_bytes(&mut reopened, 0, &[0x31; 4096]);
+    assert_bytes(&mut reopened, 60 * 1024, &[0x32; 4096]);
+    assert_bytes(&mut reopened, 252 * 1024, &[0x33; 4096]);
+    assert_bytes(&mut reopened, 1020 * 1024, &[0x34; 4096]);
+    assert_bytes(&mut reopened, 700 * 1024, &[0; 4096]);
+
+    assert!(matches!(
+        reopened.resize(512 * 1024),
+        Err(DiskError::Unsupported(_))
+    ));
+}
@@ -226,6 +226,7 @@ fn file_backend_aerospar_disk_persists_after_reopen() {
             AeroSparseConfig {
                 disk_size_bytes: (SECTOR_SIZE * 128) as u64,
                 block_size_bytes: 4096,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -0,0 +1,134 @@
+#!/usr/bin/env python3
+"""Regenerate the qcow2 backing-chain fixtures in this directory.
+
+The images use the layout `qemu-img create -f qcow2 -o cluster_size=4096` produces (v3 header,
+feature name table, optional backing format extension, backing file name in cluster 0) and
+allocate clusters in write order like `qemu-io -c 'write -P ...'`.
+"""
+
+import os
+import struct
+
+CLUSTER = 4096
+KIB = 1024
+MIB = 1024 * KIB
+
+OFLAG_COPIED = 1 << 63
+OFLAG_ZERO = 1
+
+EXT_END = 0
+EXT_BACKING_FORMAT = 0xE2792ACA
+EXT_FEATURE_TABLE = 0x6803F857
+
+FEATURES = [
+    (0, 0, b"dirty bit"),
+    (0, 1, b"corrupt bit"),
+    (0, 2, b"external data file"),
+    (0, 3, b"compression type"),
+    (0, 4, b"extended L2 entries"),
+    (1, 0, b"lazy refcounts"),
+    (2, 0, b"bitmaps"),
+    (2, 1, b"raw external data"),
+]
+
+
+def extension(ext_type, data):
+    pad = (-len(data)) % 8
+    return struct.pack(">II", ext_type, len(data)) + data + b"\0" * pad
+
+
+def image(size, writes, backing=None, backing_format=None):
+    """`writes` is a list of `(cluster_index, fill_byte)`; a fill byte of `None` is a zero cluster."""
+    header_length = 112
+    exts = b""
+    if backing_format is not None:
+        exts += extension(EXT_BACKING_FORMAT, backing_format.encode())
+    exts += extension(
+        EXT_FEATURE_TABLE,
+        b"".join(struct.pack(">BB46s", t, bit, name) for t, bit, name in FEATURES),
+    )
+    exts += struct.pack(">II", EXT_END, 0)
+
+    backing_offset = header_length + len(exts) if backing else 0
+    backing_name = backing.encode() if backing else b""
+
+    refcount_table = 1 * CLUSTER
+    refcount_block = 2 * CLUSTER
+    l1_table = 3 * CLUSTER
+    l2_table = 4 * CLUSTER
+    l2_entries = CLUSTER // 8
+    l1_size = -(-size // (CLUSTER * l2_entries))
+
+    clusters = 4 + (1 if writes else 0)
+    l2 = [0] * l2_entries
+    data = []
+    for index, fill in writes:
+        if fill is None:
+            l2[index] = OFLAG_ZERO
+        else:
+            l2[index] = clusters * CLUSTER | OFLAG_COPIED
+            data.append(bytes([fill]) * CLUSTER)
+            clusters += 1
+
+    out = bytearray(clusters * CLUSTER)
+    struct.pack_into(
+        ">4sIQIIQIIQQIIQQQQII",
+        out,
+        0,
+        b"QFI\xfb",
+        3,  # version
+        backing_offset,
+        len(backing_name),
+        12,  # cluster_bits
+        size,
+        0,  # crypt_method
+        l1_size,
+        l1_table,
+        refcount_table,
+        1,  # refcount_table_clusters
+        0,  # nb_snapshots
+        0,  # snapshots_offset
+        0,  # incompatible_features
+        0,  # compatible_features
+        0,  # autoclear_features
+        4,  # refcount_order
+        header_length,
+    )
+    out[header_length : header_length + len(exts)] = exts
+    out[backing_offset : backing_offset + len(backing_name)] = backing_name
+
+    struct.pack_into(">Q", out, refcount_table, refcount_block)
+    for cluster in range(clusters):
+        struct.pack_into(">H", out, refcount_block + cluster * 2, 1)
+    if writes:
+        struct.pack_into(">Q", out, l1_table, l2_table | OFLAG_COPIED)
+        for i, entry in enumerate(l2):
+            struct.pack_into(">Q", out, l2_table + i * 8, entry)
+    for i, cluster in enumerate(data):
+        start = (5 + i) * CLUSTER
+        out[start : start + CLUSTER] = cluster
+    return bytes(out)
+
+
+def main():
+    here = os.path.dirname(os.path.abspath(__file__))
+    images = {
+        # 0xAA at [0, 12K), 0xBB at [16K, 20K).
+        "base.qcow2": image(1 * MIB, [(0, 0xAA), (1, 0xAA), (2, 0xAA), (4, 0xBB)]),
+        # Zero cluster at [0, 4K), 0xCC at [4K, 8K). No backing format: probed by the reader.
+        "mid.qcow2": image(1 * MIB, [(0, None), (1, 0xCC)], backing="base.qcow2"),
+        # 0xDD at [12K, 16K), 0xEE at [1536K, 1540K) (past the end of the 1MiB backing file).
+        "top.qcow2": image(
+            2 * MIB,
+            [(3, 0xDD), (1536 * KIB // CLUSTER, 0xEE)],
+            backing="mid.qcow2",
+            backing_format="qcow2",
+        ),
+    }
+    for name, data in images.items():
+        with open(os.path.join(here, name), "wb") as f:
+            f.write(data)
+
+
+if __name__ == "__main__":
+    main()
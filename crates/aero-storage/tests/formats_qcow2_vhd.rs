@@ -1,9 +1,15 @@
 #![cfg(not(target_arch = "wasm32"))]
 
+use aero_storage::convert::{
+    convert_image, ConvertError, ConvertOptions, ConvertPhase, ConvertProgress, ConvertReport,
+};
 use aero_storage::{
-    detect_format, DiskError, DiskFormat, DiskImage, MemBackend, Qcow2Disk, RawDisk,
-    StorageBackend, VhdDisk, VirtualDisk, SECTOR_SIZE,
+    copy_disk, detect_format, detect_format_candidates, AeroCowDisk, AeroSparseConfig,
+    AeroSparseDisk, AllocatedRange, AllocationStatus, DetectionConfidence, DiskError, DiskFormat,
+    DiskImage, FormatCandidate, MemBackend, Qcow2Disk, RawDisk, StorageBackend, VhdDisk,
+    VirtualDisk, SECTOR_SIZE,
 };
+use std::ops::ControlFlow;
 use std::sync::{
     atomic::{AtomicU64, Ordering},
     Arc, Mutex,
@@ -687,6 +693,122 @@ fn disk_image_open_auto_with_parent_supports_qcow2_backing() {
     assert_eq!(buf0, backing_sector0);
 }
 
+#[test]
+fn disk_image_open_layered_stacks_qcow2_and_aerosparse_overlays() {
+    let virtual_size = 64 * 1024u64;
+
+    let mut base_backend = MemBackend::with_len(virtual_size).unwrap();
+    let mut base_sector0 = [0u8; SECTOR_SIZE];
+    base_sector0[..12].copy_from_slice(b"base sector0");
+    base_backend.write_at(0, &base_sector0).unwrap();
+
+    let sparse_overlay = AeroSparseDisk::create(
+        MemBackend::new(),
+        AeroSparseConfig {
+            disk_size_bytes: virtual_size,
+            block_size_bytes: 4096,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .into_backend();
+
+    let mut disk = DiskImage::open_layered(vec![
+        (base_backend, DiskFormat::Raw),
+        (
+            make_qcow2_empty_with_backing(virtual_size),
+            DiskFormat::Qcow2,
+        ),
+        (sparse_overlay, DiskFormat::AeroSparse),
+    ])
+    .unwrap();
+    assert_eq!(disk.capacity_bytes(), virtual_size);
+
+    // Reads fall through both overlays to the base.
+    let mut buf = [0u8; SECTOR_SIZE];
+    disk.read_sectors(0, &mut buf).unwrap();
+    assert_eq!(buf, base_sector0);
+
+    // Partial writes land in the top overlay, seeded from the layers below.
+    disk.write_at(4, b"top").unwrap();
+    disk.read_sectors(0, &mut buf).unwrap();
+    assert_eq!(&buf[..12], b"basetopctor0");
+}
+
+#[test]
+fn disk_image_open_layered_rejects_invalid_stacks() {
+    let virtual_size = 64 * 1024u64;
+
+    let err = DiskImage::<MemBackend>::open_layered(Vec::new())
+        .err()
+        .unwrap();
+    assert!(matches!(err, DiskError::InvalidConfig(_)));
+
+    // Raw images have no allocation map, so they cannot act as overlays.
+    let err = DiskImage::open_layered(vec![
+        (MemBackend::with_len(virtual_size).unwrap(), DiskFormat::Raw),
+        (MemBackend::with_len(virtual_size).unwrap(), DiskFormat::Raw),
+    ])
+    .err()
+    .unwrap();
+    assert!(matches!(err, DiskError::InvalidConfig(_)));
+
+    // A QCOW2 overlay must declare a backing file.
+    let err = DiskImage::open_layered(vec![
+        (MemBackend::with_len(virtual_size).unwrap(), DiskFormat::Raw),
+        (make_qcow2_empty(virtual_size), DiskFormat::Qcow2),
+    ])
+    .err()
+    .unwrap();
+    assert!(matches!(err, DiskError::InvalidConfig(_)));
+}
+
+#[test]
+fn detect_format_candidates_reports_ambiguous_signatures() {
+    // A plain QCOW2 image: one signature match plus the raw fallback.
+    let mut backend = make_qcow2_empty(64 * 1024);
+    assert_eq!(
+        detect_format_candidates(&mut backend).unwrap(),
+        vec![
+            FormatCandidate {
+                format: DiskFormat::Qcow2,
+                confidence: DetectionConfidence::Signature,
+            },
+            FormatCandidate {
+                format: DiskFormat::Raw,
+                confidence: DetectionConfidence::Fallback,
+            },
+        ]
+    );
+
+    // Append a fixed VHD footer: both signatures now match and QCOW2 keeps priority.
+    let len = backend.len().unwrap();
+    let footer = make_vhd_footer(len, 2, u64::MAX);
+    backend.write_at(len, &footer).unwrap();
+    let candidates = detect_format_candidates(&mut backend).unwrap();
+    let formats: Vec<DiskFormat> = candidates.iter().map(|c| c.format).collect();
+    assert_eq!(
+        formats,
+        vec![DiskFormat::Qcow2, DiskFormat::Vhd, DiskFormat::Raw]
+    );
+    assert_eq!(detect_format(&mut backend).unwrap(), DiskFormat::Qcow2);
+
+    // The caller can override detection and open the bytes as raw.
+    let disk = DiskImage::open_with_format(DiskFormat::Raw, backend).unwrap();
+    assert_eq!(disk.capacity_bytes(), len + SECTOR_SIZE as u64);
+
+    // Truncated images still report the format, at lower confidence.
+    let mut truncated = MemBackend::with_len(16).unwrap();
+    truncated.write_at(0, b"QFI\xfb").unwrap();
+    assert_eq!(
+        detect_format_candidates(&mut truncated).unwrap()[0],
+        FormatCandidate {
+            format: DiskFormat::Qcow2,
+            confidence: DetectionConfidence::Truncated,
+        }
+    );
+}
+
 #[test]
 fn qcow2_with_backing_reads_fall_back_and_writes_are_copy_on_write() {
     let virtual_size = 64 * 1024u64;
@@ -770,38 +892,35 @@ fn qcow2_with_backing_reads_fall_back_and_writes_are_copy_on_write() {
 }
 
 #[test]
-fn qcow2_backing_zero_cluster_flag_falls_back_to_parent() {
+fn qcow2_backing_zero_cluster_flag_does_not_fall_back_to_parent() {
     let virtual_size = 64 * 1024u64;
     let cluster_size = 1u64 << 12;
     let l2_table_offset = cluster_size * 4;
 
     let mut backing_backend = MemBackend::with_len(virtual_size).unwrap();
-    let mut backing_sector0 = [0u8; SECTOR_SIZE];
-    backing_sector0[..15].copy_from_slice(b"backing sector0");
-    backing_backend.write_at(0, &backing_sector0).unwrap();
-    let mut backing_sector1 = [0u8; SECTOR_SIZE];
-    backing_sector1[..15].copy_from_slice(b"backing sector1");
-    backing_backend
-        .write_at(SECTOR_SIZE as u64, &backing_sector1)
-        .unwrap();
-
+    backing_backend.write_at(0, &[0xAAu8; 4096 * 2]).unwrap();
     let backing_disk = RawDisk::open(backing_backend).unwrap();
 
     let mut qcow2_backend = make_qcow2_empty_with_backing(virtual_size);
-    // Mark guest cluster 0 as a v3 "zero cluster". The implementation treats this as unallocated,
-    // and with a backing disk it should therefore fall back to the parent.
+    // Mark guest cluster 0 as a v3 "zero cluster": it reads as zeros even with a backing disk.
     qcow2_backend
         .write_at(l2_table_offset, &QCOW2_OFLAG_ZERO.to_be_bytes())
         .unwrap();
 
     let mut disk = Qcow2Disk::open_with_parent(qcow2_backend, Box::new(backing_disk)).unwrap();
 
-    let mut read0 = [0u8; SECTOR_SIZE];
-    disk.read_sectors(0, &mut read0).unwrap();
-    assert_eq!(read0, backing_sector0);
+    let mut cluster = vec![0xFFu8; cluster_size as usize];
+    disk.read_at(0, &mut cluster).unwrap();
+    assert!(cluster.iter().all(|&b| b == 0));
+    // Unallocated clusters still fall through to the backing disk.
+    disk.read_at(cluster_size, &mut cluster).unwrap();
+    assert!(cluster.iter().all(|&b| b == 0xAA));
+
+    // Zero writes to a zero cluster don't allocate.
+    disk.write_sectors(0, &[0u8; SECTOR_SIZE]).unwrap();
 
-    // Writes should allocate a real data cluster and preserve backing bytes for sectors we don't
-    // overwrite.
+    // Other writes allocate a real data cluster; sectors we don't overwrite stay zero rather than
+    // exposing the backing disk.
     let data = vec![0xCCu8; SECTOR_SIZE];
     disk.write_sectors(0, &data).unwrap();
 
@@ -809,9 +928,50 @@ fn qcow2_backing_zero_cluster_flag_falls_back_to_parent() {
     disk.read_sectors(0, &mut read_back).unwrap();
     assert_eq!(read_back, data);
 
-    let mut read1 = [0u8; SECTOR_SIZE];
+    let mut read1 = [0xFFu8; SECTOR_SIZE];
     disk.read_sectors(1, &mut read1).unwrap();
-    assert_eq!(read1, backing_sector1);
+    assert_eq!(read1, [0u8; SECTOR_SIZE]);
+
+    let (qcow2_backend, _) = disk.into_backend_and_backing();
+    assert_eq!(qcow2_backend.as_slice().len() as u64, cluster_size * 6);
+}
+
+#[test]
+fn qcow2_backing_smaller_than_image_reads_zeros_past_its_end() {
+    let virtual_size = 64 * 1024u64;
+    let backing_size = 6 * 1024u64;
+
+    let mut backing_backend = MemBackend::with_len(backing_size).unwrap();
+    backing_backend
+        .write_at(0, &vec![0xABu8; backing_size as usize])
+        .unwrap();
+    let backing_disk = RawDisk::open(backing_backend).unwrap();
+
+    let mut disk = Qcow2Disk::open_with_parent(
+        make_qcow2_empty_with_backing(virtual_size),
+        Box::new(backing_disk),
+    )
+    .unwrap();
+    let mut expected = vec![0u8; virtual_size as usize];
+    expected[..backing_size as usize].fill(0xAB);
+
+    let mut all = vec![0xFFu8; virtual_size as usize];
+    disk.read_at(0, &mut all).unwrap();
+    assert_eq!(all, expected);
+
+    // A partial write into the cluster containing the backing end seeds zeros past it.
+    disk.write_at(7 * 1024, &[0x11; 16]).unwrap();
+    expected[7 * 1024..7 * 1024 + 16].fill(0x11);
+    disk.read_at(0, &mut all).unwrap();
+    assert_eq!(all, expected);
+
+    assert_eq!(
+        disk.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 8 * 1024, AllocationStatus::Data),
+            (8 * 1024, virtual_size, AllocationStatus::Unallocated),
+        ])
+    );
 }
 
 #[test]
@@ -1560,11 +1720,11 @@ fn qcow2_failed_l2_entry_write_does_not_leave_cached_mapping() {
     let data = vec![0x55u8; SECTOR_SIZE];
 
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Retry should still fail (the failed metadata update must not have been cached).
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Since the L2 entry was never persisted, reads must still return zeros.
     let mut back = vec![0xAAu8; SECTOR_SIZE];
@@ -1585,11 +1745,11 @@ fn qcow2_failed_l1_entry_write_does_not_leave_cached_l1_entry() {
     let data = vec![0x11u8; SECTOR_SIZE];
 
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Retry should still fail (the failed L1 update must not have been cached).
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Since the L1 entry was never persisted, reads must still return zeros.
     let mut back = vec![0xAAu8; SECTOR_SIZE];
@@ -1613,11 +1773,11 @@ fn qcow2_failed_refcount_table_entry_write_does_not_leave_cached_entry() {
 
     let data = vec![0x22u8; SECTOR_SIZE];
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Retry should still fail (the failed table entry must not have been cached).
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Since the mapping never completed, reads must still return zeros.
     let mut back = vec![0xAAu8; SECTOR_SIZE];
@@ -2130,7 +2290,7 @@ fn vhd_dynamic_failed_bitmap_write_rolls_back_cached_bit() {
 
     let data = vec![0x55u8; SECTOR_SIZE];
     let err = disk.write_sectors(1, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Even though the data write may have succeeded, the bitmap bit was not persisted and should
     // also have been rolled back in the in-memory cache, so reads must still return zeros.
@@ -2156,11 +2316,11 @@ fn vhd_dynamic_failed_bat_write_does_not_leave_cached_bat_entry() {
 
     let data = vec![0x11u8; SECTOR_SIZE];
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Retry should still fail (the failed BAT write must not have been cached).
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // Reads must still return zeros because the block is still unallocated in-memory.
     let mut back = vec![0xAAu8; SECTOR_SIZE];
@@ -2199,7 +2359,7 @@ fn vhd_dynamic_failed_bitmap_init_rolls_back_allocation() {
 
     let data = vec![0x11u8; SECTOR_SIZE];
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     let mut backend = disk.into_backend();
     assert_eq!(backend.len().unwrap(), initial_len);
@@ -2229,7 +2389,7 @@ fn vhd_dynamic_failed_footer_write_rolls_back_resize() {
 
     let data = vec![0x11u8; SECTOR_SIZE];
     let err = disk.write_sectors(0, &data).unwrap_err();
-    assert!(matches!(err, DiskError::Io(_)));
+    assert!(matches!(err.root(), DiskError::Io(_)));
 
     // The allocation should have rolled back the file resize so the original footer remains
     // at EOF and the image is still openable.
@@ -2958,3 +3118,392 @@ fn vhd_dynamic_rejects_dynamic_header_overlapping_footer() {
         DiskError::CorruptImage("vhd dynamic header overlaps footer")
     ));
 }
+
+/// `(start, end, status)` triples as the ranges `allocation_status` is expected to return.
+fn alloc_ranges(spec: &[(u64, u64, AllocationStatus)]) -> Vec<AllocatedRange> {
+    spec.iter()
+        .map(|&(start, end, status)| AllocatedRange {
+            offset: start,
+            len: end - start,
+            status,
+        })
+        .collect()
+}
+
+fn read_all(disk: &mut dyn VirtualDisk) -> Vec<u8> {
+    let mut buf = vec![0u8; disk.capacity_bytes() as usize];
+    disk.read_at(0, &mut buf).unwrap();
+    buf
+}
+
+fn new_sparse(virtual_size: u64) -> AeroSparseDisk<MemBackend> {
+    AeroSparseDisk::create(
+        MemBackend::new(),
+        AeroSparseConfig {
+            disk_size_bytes: virtual_size,
+            block_size_bytes: 4096,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn allocation_status_defaults_to_data_and_checks_bounds() {
+    let mut disk = RawDisk::create(MemBackend::new(), 64 * 1024).unwrap();
+    assert_eq!(
+        disk.allocation_status(0x1000, 0x2000).unwrap(),
+        alloc_ranges(&[(0x1000, 0x3000, AllocationStatus::Data)])
+    );
+    assert!(disk.allocation_status(0, 0).unwrap().is_empty());
+    assert!(matches!(
+        disk.allocation_status(0xF000, 0x2000).unwrap_err(),
+        DiskError::OutOfBounds { .. }
+    ));
+}
+
+#[test]
+fn aerosparse_to_qcow2_conversion_skips_unallocated_blocks() {
+    use AllocationStatus::{Data, Unallocated};
+
+    let virtual_size = 1024 * 1024u64;
+    let mut src = new_sparse(virtual_size);
+    src.write_at(0x3000, &[0xAA]).unwrap();
+    src.write_at(0x2_0000, &[0x55; 0x1800]).unwrap();
+    src.write_at(0xF_FFFF, &[0x11]).unwrap();
+
+    let expected = alloc_ranges(&[
+        (0, 0x3000, Unallocated),
+        (0x3000, 0x4000, Data),
+        (0x4000, 0x2_0000, Unallocated),
+        (0x2_0000, 0x2_2000, Data),
+        (0x2_2000, 0xF_F000, Unallocated),
+        (0xF_F000, 0x10_0000, Data),
+    ]);
+    assert_eq!(src.allocation_status(0, virtual_size).unwrap(), expected);
+    // Partial queries are clipped to the requested range.
+    assert_eq!(
+        src.allocation_status(0x3800, 0x1_D000).unwrap(),
+        alloc_ranges(&[
+            (0x3800, 0x4000, Data),
+            (0x4000, 0x2_0000, Unallocated),
+            (0x2_0000, 0x2_0800, Data),
+        ])
+    );
+
+    let mut dst = Qcow2Disk::open(make_qcow2_empty(virtual_size)).unwrap();
+    copy_disk(&mut src, &mut dst, true).unwrap();
+    assert_eq!(dst.allocation_status(0, virtual_size).unwrap(), expected);
+    assert_eq!(read_all(&mut dst), read_all(&mut src));
+
+    // Without skipping, every byte is copied; the contents still match.
+    let mut raw = RawDisk::create(MemBackend::new(), virtual_size).unwrap();
+    copy_disk(&mut src, &mut raw, false).unwrap();
+    assert_eq!(read_all(&mut raw), read_all(&mut src));
+
+    // The destination must be able to hold the source.
+    let mut small = new_sparse(virtual_size / 2);
+    assert!(matches!(
+        copy_disk(&mut src, &mut small, true).unwrap_err(),
+        DiskError::InvalidConfig(_)
+    ));
+}
+
+#[test]
+fn qcow2_allocation_status_distinguishes_zero_and_unallocated_clusters() {
+    use AllocationStatus::{Data, Unallocated, Zero};
+
+    let virtual_size = 64 * 1024u64;
+    let cluster_size = 1u64 << 12;
+    let l2_table_offset = cluster_size * 4;
+
+    let mut backend = make_qcow2_empty(virtual_size);
+    backend
+        .write_at(l2_table_offset + 2 * 8, &QCOW2_OFLAG_ZERO.to_be_bytes())
+        .unwrap();
+    let mut disk = Qcow2Disk::open(backend).unwrap();
+    disk.write_at(0x5000, &[0x77; 16]).unwrap();
+
+    assert_eq!(
+        disk.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 0x2000, Unallocated),
+            (0x2000, 0x3000, Zero),
+            (0x3000, 0x5000, Unallocated),
+            (0x5000, 0x6000, Data),
+            (0x6000, virtual_size, Unallocated),
+        ])
+    );
+    assert_eq!(
+        disk.allocation_status(0x2800, 0x3000).unwrap(),
+        alloc_ranges(&[
+            (0x2800, 0x3000, Zero),
+            (0x3000, 0x5000, Unallocated),
+            (0x5000, 0x5800, Data),
+        ])
+    );
+
+    // Zero clusters stay sparse in the converted image.
+    let mut dst = new_sparse(virtual_size);
+    copy_disk(&mut disk, &mut dst, true).unwrap();
+    assert_eq!(
+        dst.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 0x5000, Unallocated),
+            (0x5000, 0x6000, Data),
+            (0x6000, virtual_size, Unallocated),
+        ])
+    );
+    assert_eq!(read_all(&mut dst), read_all(&mut disk));
+}
+
+#[test]
+fn qcow2_allocation_status_reports_backing_disk_for_clusters_without_data() {
+    use AllocationStatus::{Data, Unallocated};
+
+    let virtual_size = 64 * 1024u64;
+    let mut backing = new_sparse(virtual_size);
+    backing.write_at(0x8000, &[1]).unwrap();
+
+    let mut disk = Qcow2Disk::open_with_parent(
+        make_qcow2_empty_with_backing(virtual_size),
+        Box::new(backing),
+    )
+    .unwrap();
+    disk.write_at(0x1000, &[2]).unwrap();
+
+    assert_eq!(
+        disk.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 0x1000, Unallocated),
+            (0x1000, 0x2000, Data),
+            (0x2000, 0x8000, Unallocated),
+            (0x8000, 0x9000, Data),
+            (0x9000, virtual_size, Unallocated),
+        ])
+    );
+}
+
+#[test]
+fn vhd_allocation_status_uses_bat_sector_bitmaps_and_parent() {
+    use AllocationStatus::{Data, Unallocated};
+
+    let virtual_size = 64 * 1024u64;
+    let block_size = 16 * 1024u32;
+
+    let mut fixed = VhdDisk::open(make_vhd_fixed_with_pattern()).unwrap();
+    assert_eq!(
+        fixed.allocation_status(0, fixed.capacity_bytes()).unwrap(),
+        alloc_ranges(&[(0, fixed.capacity_bytes(), Data)])
+    );
+
+    let mut dynamic = VhdDisk::open(make_vhd_dynamic_empty(virtual_size, block_size)).unwrap();
+    dynamic.write_sectors(3, &[0x33; SECTOR_SIZE]).unwrap();
+    dynamic
+        .write_at(0x8000 + 0x200, &[0x44; SECTOR_SIZE * 2])
+        .unwrap();
+    let expected = alloc_ranges(&[
+        (0, 0x600, Unallocated),
+        (0x600, 0x800, Data),
+        (0x800, 0x8200, Unallocated),
+        (0x8200, 0x8600, Data),
+        (0x8600, virtual_size, Unallocated),
+    ]);
+    assert_eq!(
+        dynamic.allocation_status(0, virtual_size).unwrap(),
+        expected
+    );
+
+    let mut dst = new_sparse(virtual_size);
+    copy_disk(&mut dynamic, &mut dst, true).unwrap();
+    assert_eq!(read_all(&mut dst), read_all(&mut dynamic));
+    assert_eq!(
+        dst.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 0x1000, Data),
+            (0x1000, 0x8000, Unallocated),
+            (0x8000, 0x9000, Data),
+            (0x9000, virtual_size, Unallocated),
+        ])
+    );
+
+    // Sectors a differencing disk does not store report the parent's allocation.
+    let mut parent = new_sparse(virtual_size);
+    parent.write_at(0xC000, &[1]).unwrap();
+    let mut child = VhdDisk::open_differencing(
+        make_vhd_differencing_empty(virtual_size, block_size),
+        Box::new(parent),
+    )
+    .unwrap();
+    child.write_sectors(3, &[0x55; SECTOR_SIZE]).unwrap();
+    assert_eq!(
+        child.allocation_status(0, virtual_size).unwrap(),
+        alloc_ranges(&[
+            (0, 0x600, Unallocated),
+            (0x600, 0x800, Data),
+            (0x800, 0xC000, Unallocated),
+            (0xC000, 0xD000, Data),
+            (0xD000, virtual_size, Unallocated),
+        ])
+    );
+}
+
+#[test]
+fn cow_allocation_status_combines_overlay_and_base() {
+    use AllocationStatus::{Data, Unallocated};
+
+    let virtual_size = 64 * 1024u64;
+    let mut base = new_sparse(virtual_size);
+    base.write_at(0x8000, &[1]).unwrap();
+
+    let mut cow = AeroCowDisk::create(base, MemBackend::new(), 4096).unwrap();
+    cow.write_at(0x1000, &[2]).unwrap();
+    cow.write_at(0x8010, &[3]).unwrap();
+    // Past the end of the base only the overlay covers the disk.
+    cow.resize(virtual_size * 2).unwrap();
+    cow.write_at(virtual_size + 0x2000, &[4]).unwrap();
+
+    let capacity = cow.capacity_bytes();
+    let expected = alloc_ranges(&[
+        (0, 0x1000, Unallocated),
+        (0x1000, 0x2000, Data),
+        (0x2000, 0x8000, Unallocated),
+        (0x8000, 0x9000, Data),
+        (0x9000, virtual_size + 0x2000, Unallocated),
+        (virtual_size + 0x2000, virtual_size + 0x3000, Data),
+        (virtual_size + 0x3000, capacity, Unallocated),
+    ]);
+    assert_eq!(cow.allocation_status(0, capacity).unwrap(), expected);
+
+    let mut dst = new_sparse(capacity);
+    copy_disk(&mut cow, &mut dst, true).unwrap();
+    assert_eq!(dst.allocation_status(0, capacity).unwrap(), expected);
+    assert_eq!(read_all(&mut dst), read_all(&mut cow));
+}
+
+fn convert(
+    src: &mut dyn VirtualDisk,
+    dst: &mut DiskImage<MemBackend>,
+    opts: ConvertOptions,
+) -> (Result<ConvertReport, ConvertError>, Vec<ConvertProgress>) {
+    let mut updates = Vec::new();
+    let result = convert_image(src, dst, opts, &mut |update| {
+        updates.push(update);
+        ControlFlow::Continue(())
+    });
+    (result, updates)
+}
+
+#[test]
+fn convert_image_aerosparse_to_qcow2_to_raw_keeps_bytes_and_sparseness() {
+    use AllocationStatus::{Data, Unallocated};
+
+    let virtual_size = 1024 * 1024u64;
+    let mut src = new_sparse(virtual_size);
+    src.write_at(0x3000, &[0xAA; 0x1000]).unwrap();
+    src.write_at(0x2_0000, &[0x55; 0x1800]).unwrap();
+    src.write_at(0xF_FFFF, &[0x11]).unwrap();
+    let expected = alloc_ranges(&[
+        (0, 0x3000, Unallocated),
+        (0x3000, 0x4000, Data),
+        (0x4000, 0x2_0000, Unallocated),
+        (0x2_0000, 0x2_2000, Data),
+        (0x2_2000, 0xF_F000, Unallocated),
+        (0xF_F000, 0x10_0000, Data),
+    ]);
+    let opts = ConvertOptions {
+        verify: true,
+        ..Default::default()
+    };
+
+    let mut qcow2 = DiskImage::Qcow2(Qcow2Disk::open(make_qcow2_empty(virtual_size)).unwrap());
+    let (report, updates) = convert(&mut src, &mut qcow2, opts);
+    assert_eq!(
+        report.unwrap(),
+        ConvertReport {
+            bytes_copied: 0x4000,
+            bytes_skipped: virtual_size - 0x4000,
+            bytes_zeroed: 0,
+            verified: true,
+        }
+    );
+    assert_eq!(qcow2.allocation_status(0, virtual_size).unwrap(), expected);
+    assert_eq!(read_all(&mut qcow2), read_all(&mut src));
+
+    let last_copy = updates
+        .iter()
+        .rfind(|u| u.phase == ConvertPhase::Copy)
+        .unwrap();
+    assert_eq!(last_copy.bytes_done, virtual_size);
+    assert_eq!(
+        last_copy.bytes_copied + last_copy.bytes_skipped,
+        virtual_size
+    );
+    let last = updates.last().unwrap();
+    assert_eq!(last.phase, ConvertPhase::Verify);
+    assert_eq!(
+        (last.bytes_done, last.bytes_total),
+        (virtual_size, virtual_size)
+    );
+
+    // A raw destination has no holes: stale bytes under the source's sparse ranges are zeroed.
+    let mut raw = RawDisk::create(MemBackend::new(), virtual_size).unwrap();
+    raw.write_at(0, &vec![0xEE; virtual_size as usize]).unwrap();
+    let mut raw = DiskImage::Raw(raw);
+    let (report, _) = convert(&mut qcow2, &mut raw, opts);
+    assert_eq!(
+        report.unwrap(),
+        ConvertReport {
+            bytes_copied: 0x4000,
+            bytes_skipped: 0,
+            bytes_zeroed: virtual_size - 0x4000,
+            verified: true,
+        }
+    );
+    assert_eq!(read_all(&mut raw), read_all(&mut src));
+}
+
+#[test]
+fn convert_image_rejects_mismatched_geometry_and_honors_cancellation() {
+    let virtual_size = 64 * 1024u64;
+    let mut src = new_sparse(virtual_size);
+    src.write_at(0, &[0x42; 0x8000]).unwrap();
+
+    // ISO media use 2048-byte sectors; every destination format uses 512.
+    let mut dst = DiskImage::Qcow2(Qcow2Disk::open(make_qcow2_empty(virtual_size)).unwrap());
+    let iso = ConvertOptions {
+        source_sector_size: 2048,
+        ..Default::default()
+    };
+    assert_eq!(
+        convert(&mut src, &mut dst, iso).0.unwrap_err(),
+        ConvertError::SectorSizeMismatch {
+            source_sector_size: 2048,
+            destination_sector_size: SECTOR_SIZE,
+        }
+    );
+
+    let mut small = DiskImage::Raw(RawDisk::create(MemBackend::new(), virtual_size / 2).unwrap());
+    assert_eq!(
+        convert(&mut src, &mut small, ConvertOptions::default())
+            .0
+            .unwrap_err(),
+        ConvertError::DestinationTooSmall {
+            source_bytes: virtual_size,
+            destination_bytes: virtual_size / 2,
+        }
+    );
+
+    // Stop after the first chunk; what was copied so far is flushed and readable.
+    let mut calls = 0;
+    let err = convert_image(&mut src, &mut dst, ConvertOptions::default(), &mut |_| {
+        calls += 1;
+        ControlFlow::Break(())
+    })
+    .unwrap_err();
+    assert_eq!(err, ConvertError::Cancelled);
+    assert_eq!(calls, 1);
+    let mut head = [0u8; 16];
+    dst.read_at(0, &mut head).unwrap();
+    assert_eq!(head, [0x42; 16]);
+}
@@ -0,0 +1,172 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use aero_storage::{
+    AeroCowDisk, DiskError, MemBackend, RawDisk, StorageBackend, VirtualDisk,
+    MEM_BACKEND_PAGE_SIZE, SECTOR_SIZE,
+};
+
+/// Tracks the bytes currently allocated by each thread, so concurrently running tests don't
+/// disturb each other's measurements.
+struct CountingAlloc;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    // `try_with` fails during thread teardown; those allocations don't matter here.
+    let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + delta));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(Cell::get)
+}
+
+fn pattern_byte(offset: usize) -> u8 {
+    (offset / MEM_BACKEND_PAGE_SIZE % 251) as u8 + 1
+}
+
+fn patterned(len: usize) -> MemBackend {
+    let mut backend = MemBackend::with_len(len as u64).unwrap();
+    let mut chunk = vec![0u8; 1024 * 1024];
+    for start in (0..len).step_by(chunk.len()) {
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b = pattern_byte(start + i);
+        }
+        backend.write_at(start as u64, &chunk).unwrap();
+    }
+    backend
+}
+
+#[test]
+fn forking_a_256mib_backend_20_times_keeps_memory_flat() {
+    const LEN: usize = 256 * 1024 * 1024;
+    let mut base = patterned(LEN);
+    // The first fork pages the base; measure from there.
+    drop(base.fork().unwrap());
+    let before = live_bytes();
+
+    let mut forks = Vec::new();
+    for i in 0..20u8 {
+        let mut fork = base.fork().unwrap();
+        fork.write_at(u64::from(i) * 4096 + 10, &[0xEE; 16])
+            .unwrap();
+        fork.write_at(LEN as u64 - 512, &[i; 512]).unwrap();
+        forks.push(fork);
+    }
+    let grown = live_bytes() - before;
+    // Each fork costs its page table plus the two pages it wrote; a full copy would be 256 MiB.
+    assert!(grown < 32 * 1024 * 1024, "20 forks allocated {grown} bytes");
+
+    // Forks see their own writes; everything else still reads the template.
+    for (i, fork) in forks.iter_mut().enumerate() {
+        let mut buf = [0u8; 32];
+        fork.read_at(i as u64 * 4096, &mut buf).unwrap();
+        assert_eq!(buf[..10], [pattern_byte(i * 4096); 10]);
+        assert_eq!(buf[10..26], [0xEE; 16]);
+        let mut tail = [0u8; 512];
+        fork.read_at(LEN as u64 - 512, &mut tail).unwrap();
+        assert_eq!(tail, [i as u8; 512]);
+    }
+    let mut buf = [0u8; 32];
+    base.read_at(10, &mut buf).unwrap();
+    assert_eq!(buf, [pattern_byte(10); 32]);
+
+    // Reading shared pages does not allocate.
+    let mut big = vec![0u8; 4 * MEM_BACKEND_PAGE_SIZE];
+    let before_read = live_bytes();
+    forks[0]
+        .read_at(3 * MEM_BACKEND_PAGE_SIZE as u64 + 7, &mut big)
+        .unwrap();
+    assert_eq!(live_bytes(), before_read);
+}
+
+#[test]
+fn writes_to_the_parent_do_not_leak_into_forks() {
+    let mut parent = MemBackend::from_vec(vec![0x11; 3 * MEM_BACKEND_PAGE_SIZE]);
+    let mut child = parent.fork().unwrap();
+    let mut grandchild = child.fork().unwrap();
+
+    parent.write_at(100, &[0x22; 8]).unwrap();
+    child.write_at(100, &[0x33; 8]).unwrap();
+
+    let read8 = |backend: &mut MemBackend| {
+        let mut buf = [0u8; 8];
+        backend.read_at(100, &mut buf).unwrap();
+        buf
+    };
+    assert_eq!(read8(&mut parent), [0x22; 8]);
+    assert_eq!(read8(&mut child), [0x33; 8]);
+    assert_eq!(read8(&mut grandchild), [0x11; 8]);
+
+    // Shrinking then growing a fork exposes zeros, not the shared page's old bytes.
+    grandchild.set_len(50).unwrap();
+    grandchild.set_len(200).unwrap();
+    assert_eq!(read8(&mut grandchild), [0; 8]);
+    assert_eq!(read8(&mut child), [0x33; 8]);
+
+    let mut expected = vec![0x11; 3 * MEM_BACKEND_PAGE_SIZE];
+    expected[100..108].fill(0x33);
+    assert_eq!(child.as_slice(), expected.as_slice());
+    assert_eq!(child.into_vec(), expected);
+}
+
+#[test]
+fn frozen_base_is_shared_by_cow_overlays() {
+    let mut template = RawDisk::create(MemBackend::new(), 64 * SECTOR_SIZE as u64).unwrap();
+    template.write_sectors(3, &[0xAB; SECTOR_SIZE]).unwrap();
+    let frozen = template.into_backend().freeze().unwrap();
+
+    let mut overlays: Vec<_> = (0..4u8)
+        .map(|i| {
+            let base = RawDisk::open(frozen.clone()).unwrap();
+            let mut cow = AeroCowDisk::create(base, MemBackend::new(), 4096).unwrap();
+            cow.write_sectors(3, &[i; SECTOR_SIZE]).unwrap();
+            cow
+        })
+        .collect();
+
+    let mut sector = [0u8; SECTOR_SIZE];
+    for (i, cow) in overlays.iter_mut().enumerate() {
+        cow.read_sectors(3, &mut sector).unwrap();
+        assert_eq!(sector, [i as u8; SECTOR_SIZE]);
+    }
+    let mut base = RawDisk::open(frozen.clone()).unwrap();
+    base.read_sectors(3, &mut sector).unwrap();
+    assert_eq!(sector, [0xAB; SECTOR_SIZE]);
+
+    let mut frozen_backend = frozen.clone();
+    assert!(matches!(
+        frozen_backend.write_at(0, &[1]),
+        Err(DiskError::NotSupported(_))
+    ));
+    let mut writable = frozen.fork();
+    writable.write_at(0, &[1]).unwrap();
+    assert_eq!(writable.len().unwrap(), 64 * SECTOR_SIZE as u64);
+}
@@ -0,0 +1,140 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_storage::{
+    DiskError, DiskFormat, DiskImage, MmapFileBackend, StorageBackend, VirtualDisk, SECTOR_SIZE,
+};
+use tempfile::tempdir;
+
+#[test]
+fn mmap_file_backend_grow_write_beyond_old_len_and_reopen() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("disk.img");
+
+    {
+        let mut backend = MmapFileBackend::create(&path, 4096).unwrap();
+        assert_eq!(backend.len().unwrap(), 4096);
+        #[cfg(unix)]
+        assert!(backend.is_mapped());
+
+        backend.write_at(100, b"head").unwrap();
+
+        // Writing past the end grows the file (and remaps it).
+        backend.write_at(10_000, b"tail").unwrap();
+        assert_eq!(backend.len().unwrap(), 10_004);
+
+        // Bytes between the old end and the write are zero-filled.
+        let mut gap = vec![0xFFu8; 10_000 - 4096];
+        backend.read_at(4096, &mut gap).unwrap();
+        assert!(gap.iter().all(|&b| b == 0));
+
+        // Explicit growth keeps existing contents.
+        backend.set_len(64 * 1024).unwrap();
+        let mut head = [0u8; 4];
+        backend.read_at(100, &mut head).unwrap();
+        assert_eq!(&head, b"head");
+
+        let err = backend.read_at(64 * 1024 - 2, &mut head).unwrap_err();
+        assert!(matches!(err, DiskError::OutOfBounds { .. }));
+        // Dropped without an explicit flush: `Drop` must write the data back.
+    }
+
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 64 * 1024);
+
+    let mut backend = MmapFileBackend::open_rw(&path).unwrap();
+    let mut buf = [0u8; 4];
+    backend.read_at(100, &mut buf).unwrap();
+    assert_eq!(&buf, b"head");
+    backend.read_at(10_000, &mut buf).unwrap();
+    assert_eq!(&buf, b"tail");
+
+    // Shrinking discards the tail.
+    backend.set_len(200).unwrap();
+    backend.flush().unwrap();
+    drop(backend);
+    assert_eq!(std::fs::read(&path).unwrap()[100..104], *b"head");
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 200);
+}
+
+#[test]
+fn mmap_file_backend_read_only_refuses_writes() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ro.img");
+    std::fs::write(&path, b"read only contents").unwrap();
+
+    let mut backend = MmapFileBackend::open_read_only(&path).unwrap();
+    assert!(backend.is_read_only());
+
+    let mut buf = [0u8; 4];
+    backend.read_at(5, &mut buf).unwrap();
+    assert_eq!(&buf, b"only");
+
+    assert!(matches!(
+        backend.write_at(0, b"x").unwrap_err(),
+        DiskError::NotSupported(_)
+    ));
+    assert!(matches!(
+        backend.set_len(0).unwrap_err(),
+        DiskError::NotSupported(_)
+    ));
+    backend.flush().unwrap();
+    drop(backend);
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"read only contents");
+}
+
+#[test]
+fn mmap_file_backend_empty_file_grows_from_zero() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("empty.img");
+
+    let mut backend = MmapFileBackend::create(&path, 0).unwrap();
+    assert_eq!(backend.len().unwrap(), 0);
+    assert!(!backend.is_mapped());
+
+    backend.write_at(0, b"abc").unwrap();
+    let mut buf = [0u8; 3];
+    backend.read_at(0, &mut buf).unwrap();
+    assert_eq!(&buf, b"abc");
+}
+
+#[test]
+fn mmap_file_backend_handles_offsets_beyond_4gib() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("large.img");
+
+    // Sparse on common filesystems: only the touched pages are allocated.
+    let len = 5 * 1024 * 1024 * 1024u64;
+    let mut backend = MmapFileBackend::create(&path, len).unwrap();
+
+    let offset = 4 * 1024 * 1024 * 1024u64 + 123;
+    backend.write_at(offset, b"beyond 4GiB").unwrap();
+    backend.flush().unwrap();
+    drop(backend);
+
+    let mut backend = MmapFileBackend::open_read_only(&path).unwrap();
+    assert_eq!(backend.len().unwrap(), len);
+    let mut buf = [0u8; 11];
+    backend.read_at(offset, &mut buf).unwrap();
+    assert_eq!(&buf, b"beyond 4GiB");
+}
+
+#[test]
+fn mmap_file_backend_opens_raw_image_via_disk_image() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("raw.img");
+
+    {
+        let backend = MmapFileBackend::create(&path, 16 * SECTOR_SIZE as u64).unwrap();
+        let mut disk = DiskImage::open_auto(backend).unwrap();
+        assert_eq!(disk.format(), DiskFormat::Raw);
+        disk.write_sectors(3, &[0x5A; SECTOR_SIZE]).unwrap();
+        disk.flush().unwrap();
+    }
+
+    let backend = MmapFileBackend::open_read_only(&path).unwrap();
+    let mut disk = DiskImage::open_auto(backend).unwrap();
+    assert_eq!(disk.capacity_bytes(), 16 * SECTOR_SIZE as u64);
+    let mut sector = [0u8; SECTOR_SIZE];
+    disk.read_sectors(3, &mut sector).unwrap();
+    assert_eq!(sector, [0x5A; SECTOR_SIZE]);
+}
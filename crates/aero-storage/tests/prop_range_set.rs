@@ -0,0 +1,232 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::time::{Duration, Instant};
+
+use aero_storage::{ByteRange, RangeSet};
+use proptest::prelude::*;
+
+/// Small universe so the reference model can be a plain bitmap.
+const UNIVERSE: u64 = 256;
+const MAX_OPS_PER_CASE: usize = 64;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Insert(u64, u64),
+    Remove(u64, u64),
+    InsertMany(Vec<(u64, u64)>),
+}
+
+/// Naive reference: one bool per byte.
+#[derive(Clone)]
+struct Model {
+    bits: Vec<bool>,
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            bits: vec![false; UNIVERSE as usize],
+        }
+    }
+
+    fn set(&mut self, start: u64, end: u64, value: bool) {
+        for b in start..end.max(start) {
+            self.bits[b as usize] = value;
+        }
+    }
+
+    fn ranges_where(&self, value: bool, bounds: ByteRange) -> Vec<ByteRange> {
+        let mut out = Vec::new();
+        let mut run: Option<u64> = None;
+        for b in bounds.start..bounds.end.max(bounds.start) {
+            match (self.bits[b as usize] == value, run) {
+                (true, None) => run = Some(b),
+                (false, Some(start)) => {
+                    out.push(ByteRange::new(start, b));
+                    run = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = run {
+            out.push(ByteRange::new(start, bounds.end));
+        }
+        out
+    }
+
+    fn ranges(&self) -> Vec<ByteRange> {
+        self.ranges_where(true, ByteRange::new(0, UNIVERSE))
+    }
+
+    fn contains(&self, start: u64, end: u64) -> bool {
+        (start..end.max(start)).all(|b| self.bits[b as usize])
+    }
+}
+
+fn range_strategy() -> impl Strategy<Value = (u64, u64)> {
+    // Reversed and empty ranges are allowed and must be ignored.
+    (0u64..=UNIVERSE, 0u64..=UNIVERSE)
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => range_strategy().prop_map(|(s, e)| Op::Insert(s, e)),
+        2 => range_strategy().prop_map(|(s, e)| Op::Remove(s, e)),
+        1 => proptest::collection::vec(range_strategy(), 0..16).prop_map(Op::InsertMany),
+    ]
+}
+
+fn build(ops: &[Op]) -> (RangeSet, Model) {
+    let mut set = RangeSet::new();
+    let mut model = Model::new();
+    for op in ops {
+        match op {
+            Op::Insert(s, e) => {
+                set.insert(*s, *e);
+                model.set(*s, *e, true);
+            }
+            Op::Remove(s, e) => {
+                set.remove(*s, *e);
+                model.set(*s, *e, false);
+            }
+            Op::InsertMany(ranges) => {
+                set.insert_many(ranges.iter().map(|&(s, e)| ByteRange::new(s, e)));
+                for &(s, e) in ranges {
+                    model.set(s, e, true);
+                }
+            }
+        }
+    }
+    (set, model)
+}
+
+fn assert_matches(set: &RangeSet, model: &Model) -> Result<(), TestCaseError> {
+    let expected = model.ranges();
+    prop_assert_eq!(set.ranges(), expected.as_slice());
+    prop_assert_eq!(set.iter().collect::<Vec<_>>(), expected.clone());
+    prop_assert_eq!(set.len(), expected.len());
+    prop_assert_eq!(set.is_empty(), expected.is_empty());
+    prop_assert_eq!(
+        set.total_len(),
+        expected.iter().map(ByteRange::len).sum::<u64>()
+    );
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 256,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn range_set_matches_reference(
+        ops in proptest::collection::vec(op_strategy(), 0..MAX_OPS_PER_CASE),
+        queries in proptest::collection::vec(range_strategy(), 0..16),
+    ) {
+        let (set, model) = build(&ops);
+        assert_matches(&set, &model)?;
+
+        for (s, e) in queries {
+            prop_assert_eq!(set.contains_range(s, e), model.contains(s, e));
+
+            let bounds = ByteRange::new(s, e);
+            let expected_gaps = model.ranges_where(false, bounds);
+            prop_assert_eq!(set.gaps(s, e).collect::<Vec<_>>(), expected_gaps.clone());
+
+            let inverted = set.invert_within(bounds);
+            prop_assert_eq!(inverted.ranges(), expected_gaps.as_slice());
+        }
+    }
+
+    #[test]
+    fn intersect_with_matches_reference(
+        a_ops in proptest::collection::vec(op_strategy(), 0..MAX_OPS_PER_CASE),
+        b_ops in proptest::collection::vec(op_strategy(), 0..MAX_OPS_PER_CASE),
+    ) {
+        let (mut a, a_model) = build(&a_ops);
+        let (b, b_model) = build(&b_ops);
+
+        let mut expected = Model::new();
+        for (bit, (&x, &y)) in expected
+            .bits
+            .iter_mut()
+            .zip(a_model.bits.iter().zip(b_model.bits.iter()))
+        {
+            *bit = x && y;
+        }
+
+        a.intersect_with(&b);
+        assert_matches(&a, &expected)?;
+    }
+
+    #[test]
+    fn serde_roundtrip_normalizes(
+        raw in proptest::collection::vec(range_strategy(), 0..32),
+    ) {
+        // Persisted manifests may contain unsorted/overlapping entries; loading must normalize them.
+        let json = serde_json::json!({
+            "ranges": raw
+                .iter()
+                .map(|&(start, end)| serde_json::json!({ "start": start, "end": end }))
+                .collect::<Vec<_>>(),
+        });
+        let set: RangeSet = serde_json::from_value(json).unwrap();
+
+        let mut model = Model::new();
+        for &(s, e) in &raw {
+            model.set(s, e, true);
+        }
+        assert_matches(&set, &model)?;
+
+        let roundtrip: RangeSet =
+            serde_json::from_str(&serde_json::to_string(&set).unwrap()).unwrap();
+        prop_assert_eq!(roundtrip, set);
+    }
+}
+
+#[test]
+fn gaps_handle_u64_max_bounds() {
+    let mut set = RangeSet::new();
+    set.insert(u64::MAX - 10, u64::MAX);
+
+    assert_eq!(
+        set.gaps(u64::MAX - 20, u64::MAX).collect::<Vec<_>>(),
+        vec![ByteRange::new(u64::MAX - 20, u64::MAX - 10)]
+    );
+    assert_eq!(set.gaps(u64::MAX - 5, u64::MAX).count(), 0);
+    assert!(set.contains_range(u64::MAX - 10, u64::MAX));
+}
+
+/// Simple LCG so the timed test doesn't depend on an RNG crate.
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 16
+}
+
+#[test]
+fn insert_one_million_random_ranges_is_fast() {
+    const IMAGE_SIZE: u64 = 40 * 1024 * 1024 * 1024;
+    const COUNT: usize = 1_000_000;
+
+    let mut seed = 0x5eed_u64;
+    let mut set = RangeSet::new();
+    let started = Instant::now();
+    for _ in 0..COUNT {
+        let start = lcg(&mut seed) % IMAGE_SIZE;
+        let len = 512 + lcg(&mut seed) % 4096;
+        set.insert(start, start + len);
+    }
+    let elapsed = started.elapsed();
+
+    // Mostly-disjoint small ranges over a 40 GiB image: the set should stay highly fragmented.
+    assert!(set.len() > COUNT / 2, "only {} ranges", set.len());
+    // The old `Vec`-based implementation was quadratic and took minutes here; keep a generous
+    // budget so unoptimized builds on slow CI machines still pass.
+    assert!(
+        elapsed < Duration::from_secs(30),
+        "inserting {COUNT} ranges took {elapsed:?}"
+    );
+}
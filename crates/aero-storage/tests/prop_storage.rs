@@ -138,6 +138,7 @@ proptest! {
             AeroSparseConfig {
                 disk_size_bytes: capacity,
                 block_size_bytes: 4096,
+                ..Default::default()
             },
         ).unwrap();
 
@@ -0,0 +1,220 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_storage::{
+    AllocatedRange, AllocationStatus, DiskError, MemBackend, Qcow2BackingFileInfo, Qcow2Disk,
+    RawDisk, Result, VirtualDisk,
+};
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+const BASE: &[u8] = include_bytes!("fixtures/qcow2_chain/base.qcow2");
+const MID: &[u8] = include_bytes!("fixtures/qcow2_chain/mid.qcow2");
+const TOP: &[u8] = include_bytes!("fixtures/qcow2_chain/top.qcow2");
+
+fn fixture(name: &str) -> Result<Box<dyn VirtualDisk>> {
+    let bytes = match name {
+        "base.qcow2" => BASE,
+        "mid.qcow2" => MID,
+        "top.qcow2" => TOP,
+        _ => return Err(DiskError::InvalidConfig("unknown backing file")),
+    };
+    Ok(Box::new(RawDisk::open(MemBackend::from_vec(
+        bytes.to_vec(),
+    ))?))
+}
+
+fn open_chain(top: Vec<u8>, opened: &mut Vec<String>) -> Qcow2Disk<MemBackend> {
+    Qcow2Disk::open_with_resolver(MemBackend::from_vec(top), &mut |name: &str| {
+        opened.push(name.to_string());
+        fixture(name)
+    })
+    .unwrap()
+}
+
+/// Guest view of `top.qcow2` as documented in `fixtures/qcow2_chain/README.md`.
+fn expected_top() -> Vec<u8> {
+    let mut expected = vec![0u8; (2 * MIB) as usize];
+    let mut fill = |start: u64, end: u64, byte: u8| {
+        expected[start as usize..end as usize].fill(byte);
+    };
+    // [0, 4K) is a zero cluster in mid.qcow2 and hides base.qcow2's 0xAA.
+    fill(4 * KIB, 8 * KIB, 0xCC);
+    fill(8 * KIB, 12 * KIB, 0xAA);
+    fill(12 * KIB, 16 * KIB, 0xDD);
+    fill(16 * KIB, 20 * KIB, 0xBB);
+    fill(1536 * KIB, 1540 * KIB, 0xEE);
+    expected
+}
+
+fn read_all(disk: &mut dyn VirtualDisk) -> Vec<u8> {
+    let mut buf = vec![0u8; disk.capacity_bytes() as usize];
+    disk.read_at(0, &mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn fixtures_report_backing_file_info() {
+    let top = Qcow2Disk::open_with_parent(
+        MemBackend::from_vec(TOP.to_vec()),
+        Box::new(RawDisk::create(MemBackend::new(), MIB).unwrap()),
+    )
+    .unwrap();
+    assert_eq!(
+        top.backing_file_info(),
+        Some(&Qcow2BackingFileInfo {
+            file_name: "mid.qcow2".to_string(),
+            format: Some("qcow2".to_string()),
+        })
+    );
+
+    let mut opened = Vec::new();
+    let mid = open_chain(MID.to_vec(), &mut opened);
+    assert_eq!(
+        mid.backing_file_info(),
+        Some(&Qcow2BackingFileInfo {
+            file_name: "base.qcow2".to_string(),
+            format: None,
+        })
+    );
+    assert_eq!(opened, ["base.qcow2"]);
+
+    let base = Qcow2Disk::open(MemBackend::from_vec(BASE.to_vec())).unwrap();
+    assert_eq!(base.backing_file_info(), None);
+}
+
+#[test]
+fn resolver_opens_the_whole_chain() {
+    let mut opened = Vec::new();
+    let mut disk = open_chain(TOP.to_vec(), &mut opened);
+    assert_eq!(opened, ["mid.qcow2", "base.qcow2"]);
+    assert_eq!(disk.capacity_bytes(), 2 * MIB);
+    assert_eq!(read_all(&mut disk), expected_top());
+
+    // Unaligned reads straddling every kind of cluster take the partial-cluster path.
+    let mut buf = vec![0u8; (20 * KIB) as usize - 2];
+    disk.read_at(1, &mut buf).unwrap();
+    assert_eq!(buf, expected_top()[1..(20 * KIB) as usize - 1]);
+
+    // Reads straddling the end of the 1MiB backing file.
+    let mut buf = vec![0xFFu8; (8 * KIB) as usize];
+    disk.read_at(MIB - 4 * KIB + 3, &mut buf).unwrap();
+    assert!(buf.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn chain_allocation_status_follows_backing_files() {
+    use AllocationStatus::{Data, Unallocated, Zero};
+
+    let mut disk = open_chain(TOP.to_vec(), &mut Vec::new());
+    let spec = [
+        (0, 4 * KIB, Zero),
+        (4 * KIB, 20 * KIB, Data),
+        (20 * KIB, 1536 * KIB, Unallocated),
+        (1536 * KIB, 1540 * KIB, Data),
+        (1540 * KIB, 2 * MIB, Unallocated),
+    ];
+    let expected: Vec<AllocatedRange> = spec
+        .iter()
+        .map(|&(start, end, status)| AllocatedRange {
+            offset: start,
+            len: end - start,
+            status,
+        })
+        .collect();
+    assert_eq!(disk.allocation_status(0, 2 * MIB).unwrap(), expected);
+}
+
+#[test]
+fn writes_round_trip_through_the_overlay() {
+    let mut disk = open_chain(TOP.to_vec(), &mut Vec::new());
+    let mut expected = expected_top();
+
+    // Partial writes over clusters resolved from each layer seed the new cluster from the chain.
+    for (offset, byte) in [
+        (100u64, 0x11u8),      // mid.qcow2 zero cluster
+        (4 * KIB + 200, 0x22), // mid.qcow2 data
+        (8 * KIB + 300, 0x33), // base.qcow2 data
+        (MIB + 400, 0x44),     // past the end of the backing file
+    ] {
+        disk.write_at(offset, &[byte; 16]).unwrap();
+        expected[offset as usize..offset as usize + 16].fill(byte);
+    }
+    // Zero writes over backing data must hide it.
+    disk.write_at(16 * KIB, &[0u8; 512]).unwrap();
+    expected[(16 * KIB) as usize..(16 * KIB) as usize + 512].fill(0);
+
+    assert_eq!(read_all(&mut disk), expected);
+    disk.flush().unwrap();
+
+    let (backend, _) = disk.into_backend_and_backing();
+    let mut reopened = open_chain(backend.into_vec(), &mut Vec::new());
+    assert_eq!(read_all(&mut reopened), expected);
+}
+
+#[test]
+fn set_backing_attaches_a_parent_after_open() {
+    let mut disk = Qcow2Disk::open_with_parent(
+        MemBackend::from_vec(TOP.to_vec()),
+        Box::new(RawDisk::create(MemBackend::new(), MIB).unwrap()),
+    )
+    .unwrap();
+    assert_eq!(read_all(&mut disk)[(8 * KIB) as usize], 0);
+
+    disk.set_backing(Box::new(open_chain(MID.to_vec(), &mut Vec::new())))
+        .unwrap();
+    assert_eq!(read_all(&mut disk), expected_top());
+
+    let mut base = Qcow2Disk::open(MemBackend::from_vec(BASE.to_vec())).unwrap();
+    assert!(matches!(
+        base.set_backing(fixture("mid.qcow2").unwrap()),
+        Err(DiskError::InvalidConfig(_))
+    ));
+    assert_eq!(base.backing_file_info(), None);
+    assert_eq!(read_all(&mut base)[0], 0xAA);
+}
+
+#[test]
+fn backing_chain_cycles_are_rejected() {
+    // Every backing file name resolves to top.qcow2 again.
+    let mut calls = 0;
+    let res = Qcow2Disk::open_with_resolver(MemBackend::from_vec(TOP.to_vec()), &mut |_: &str| {
+        calls += 1;
+        fixture("top.qcow2")
+    });
+    assert!(matches!(
+        res,
+        Err(DiskError::Unsupported("qcow2 backing chain too deep"))
+    ));
+    assert_eq!(calls, 16);
+}
+
+#[test]
+fn resolver_errors_are_propagated() {
+    let res = Qcow2Disk::open_with_resolver(MemBackend::from_vec(TOP.to_vec()), &mut |_: &str| {
+        Err(DiskError::NotSupported("no such file".into()))
+    });
+    assert!(matches!(res, Err(DiskError::NotSupported(_))));
+}
+
+#[test]
+fn raw_backing_files_are_used_directly() {
+    // Rewrite the recorded backing format to `raw` in place (both pad to 8 bytes).
+    let mut top = TOP.to_vec();
+    let ext = top
+        .windows(4)
+        .position(|w| w == [0xE2, 0x79, 0x2A, 0xCA])
+        .unwrap();
+    top[ext + 4..ext + 8].copy_from_slice(&3u32.to_be_bytes());
+    top[ext + 8..ext + 13].copy_from_slice(b"raw\0\0");
+
+    let mut disk = open_chain(top, &mut Vec::new());
+    assert_eq!(
+        disk.backing_file_info().unwrap().format.as_deref(),
+        Some("raw")
+    );
+    // The "raw" backing file is mid.qcow2's container bytes, starting with the QCOW2 magic.
+    let mut head = [0u8; 4];
+    disk.read_at(0, &mut head).unwrap();
+    assert_eq!(&head, b"QFI\xfb");
+}
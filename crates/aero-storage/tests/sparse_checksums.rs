@@ -0,0 +1,258 @@
+use aero_storage::{
+    AeroSparseConfig, AeroSparseDisk, ChecksumMismatchPolicy, DiskError, MemBackend, Result,
+    ScrubReport, StorageBackend, VirtualDisk,
+};
+
+const BLOCK_SIZE: u64 = 4096;
+
+fn create(blocks: u64, block_checksums: bool) -> AeroSparseDisk<MemBackend> {
+    AeroSparseDisk::create(
+        MemBackend::new(),
+        AeroSparseConfig {
+            disk_size_bytes: blocks * BLOCK_SIZE,
+            block_size_bytes: BLOCK_SIZE as u32,
+            block_checksums,
+        },
+    )
+    .unwrap()
+}
+
+fn read_all(disk: &mut impl VirtualDisk) -> Vec<u8> {
+    let mut buf = vec![0u8; disk.capacity_bytes() as usize];
+    disk.read_at(0, &mut buf).unwrap();
+    buf
+}
+
+/// Writes blocks 0, 2 and 3 (in that order, so they occupy physical slots 0, 1 and 2) and returns
+/// the raw image plus the expected guest view.
+fn image_with_three_blocks() -> (Vec<u8>, Vec<u8>) {
+    let mut disk = create(4, true);
+    let mut expected = vec![0u8; (4 * BLOCK_SIZE) as usize];
+    for (block, byte) in [(0u64, 0x11u8), (2, 0x22), (3, 0x33)] {
+        let off = (block * BLOCK_SIZE + 100) as usize;
+        disk.write_at(off as u64, &[byte; 1000]).unwrap();
+        expected[off..off + 1000].fill(byte);
+    }
+    (disk.into_backend().into_vec(), expected)
+}
+
+fn flip_byte_in_slot(image: &mut [u8], slot: u64, offset_in_block: u64) {
+    let header = aero_storage::AeroSparseHeader::decode(image).unwrap();
+    image[(header.data_offset + slot * BLOCK_SIZE + offset_in_block) as usize] ^= 0x01;
+}
+
+#[test]
+fn images_without_checksums_stay_version_1() {
+    let mut disk = create(4, false);
+    assert_eq!(disk.header().version, 1);
+    assert_eq!(disk.header().flags, 0);
+    assert!(!disk.header().has_block_checksums());
+    assert_eq!(disk.header().data_offset, BLOCK_SIZE);
+    assert!(matches!(
+        disk.scrub(|_, _| true),
+        Err(DiskError::Unsupported(_))
+    ));
+
+    disk.write_at(10, &[0xAB; 10]).unwrap();
+    let mut reopened = AeroSparseDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(read_all(&mut reopened)[10..20], [0xAB; 10]);
+}
+
+#[test]
+fn checksummed_writes_round_trip_and_scrub_clean() {
+    let mut disk = create(4, true);
+    assert_eq!(disk.header().version, 2);
+    assert!(disk.header().has_block_checksums());
+
+    let mut expected = vec![0u8; (4 * BLOCK_SIZE) as usize];
+    let writes: [(u64, usize, u8); 5] = [
+        (0, BLOCK_SIZE as usize, 0x10),  // full block into a new block
+        (BLOCK_SIZE - 7, 20, 0x20),      // straddles blocks 0 and 1
+        (3 * BLOCK_SIZE + 500, 1, 0x30), // single byte into a new block
+        (3 * BLOCK_SIZE + 500, 64, 0),   // zeros over existing data
+        (2 * BLOCK_SIZE, BLOCK_SIZE as usize, 0), // all-zero block stays unallocated
+    ];
+    for (offset, len, byte) in writes {
+        disk.write_at(offset, &vec![byte; len]).unwrap();
+        expected[offset as usize..offset as usize + len].fill(byte);
+    }
+    assert!(!disk.is_block_allocated(2));
+    assert_eq!(read_all(&mut disk), expected);
+
+    let mut reopened = AeroSparseDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(read_all(&mut reopened), expected);
+    let mut calls = Vec::new();
+    let report = reopened
+        .scrub(|done, total| {
+            calls.push((done, total));
+            true
+        })
+        .unwrap();
+    assert_eq!(
+        report,
+        ScrubReport {
+            blocks_checked: 3,
+            checksums_recorded: 0,
+            corrupt_blocks: Vec::new(),
+            completed: true,
+        }
+    );
+    assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+}
+
+#[test]
+fn flipped_byte_fails_reads_of_that_block_only() {
+    let (mut image, expected) = image_with_three_blocks();
+    // Block 2 lives in physical slot 1; corrupt a byte it never wrote.
+    flip_byte_in_slot(&mut image, 1, 4000);
+
+    let mut disk = AeroSparseDisk::open(MemBackend::from_vec(image)).unwrap();
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    disk.read_at(0, &mut buf).unwrap();
+    assert_eq!(buf, expected[..BLOCK_SIZE as usize]);
+    disk.read_at(3 * BLOCK_SIZE, &mut buf).unwrap();
+    assert_eq!(buf, expected[(3 * BLOCK_SIZE) as usize..]);
+
+    // Any read touching block 2 names it, also on retry.
+    for _ in 0..2 {
+        let err = disk
+            .read_at(2 * BLOCK_SIZE + 10, &mut [0u8; 16])
+            .unwrap_err();
+        match err {
+            DiskError::ChecksumMismatch {
+                block_idx,
+                expected,
+                actual,
+            } => {
+                assert_eq!(block_idx, 2);
+                assert_ne!(expected, actual);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+    assert!(matches!(
+        disk.read_at(BLOCK_SIZE, &mut vec![0u8; 2 * BLOCK_SIZE as usize]),
+        Err(DiskError::ChecksumMismatch { block_idx: 2, .. })
+    ));
+
+    let report = disk.scrub(|_, _| true).unwrap();
+    assert_eq!(report.blocks_checked, 3);
+    assert_eq!(report.corrupt_blocks, [2]);
+    assert!(report.completed);
+}
+
+#[test]
+fn log_and_continue_returns_stored_data() {
+    let (mut image, mut expected) = image_with_three_blocks();
+    flip_byte_in_slot(&mut image, 0, 100);
+    expected[100] ^= 0x01;
+
+    let mut disk = AeroSparseDisk::open(MemBackend::from_vec(image)).unwrap();
+    disk.set_checksum_mismatch_policy(ChecksumMismatchPolicy::LogAndContinue);
+    assert_eq!(read_all(&mut disk), expected);
+    assert_eq!(read_all(&mut disk), expected);
+    assert_eq!(disk.checksum_mismatches(), 1);
+}
+
+#[test]
+fn scrub_can_stop_early() {
+    let (image, _) = image_with_three_blocks();
+    let mut disk = AeroSparseDisk::open(MemBackend::from_vec(image)).unwrap();
+    let report = disk.scrub(|done, _| done < 2).unwrap();
+    assert_eq!(report.blocks_checked, 2);
+    assert!(!report.completed);
+}
+
+/// Fails every write after the first `remaining` ones, like a crash partway through an update.
+struct CrashingBackend {
+    inner: MemBackend,
+    remaining: usize,
+}
+
+impl StorageBackend for CrashingBackend {
+    fn len(&mut self) -> Result<u64> {
+        self.inner.len()
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.inner.set_len(len)
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        if self.remaining == 0 {
+            return Err(DiskError::Io("crashed".into()));
+        }
+        self.remaining -= 1;
+        self.inner.write_at(offset, buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn crash_after_data_write_is_not_reported_as_corruption() {
+    let (image, mut expected) = image_with_three_blocks();
+
+    // A partial overwrite clears the checksum entry, writes the data, then records the new
+    // checksum. Crash before the last step.
+    let mut disk = AeroSparseDisk::open(CrashingBackend {
+        inner: MemBackend::from_vec(image),
+        remaining: 2,
+    })
+    .unwrap();
+    assert!(disk.write_at(3 * BLOCK_SIZE + 8, &[0x44; 8]).is_err());
+    expected[(3 * BLOCK_SIZE + 8) as usize..(3 * BLOCK_SIZE + 16) as usize].fill(0x44);
+
+    let mut disk = AeroSparseDisk::open(disk.into_backend().inner).unwrap();
+    assert_eq!(read_all(&mut disk), expected);
+
+    let report = disk.scrub(|_, _| true).unwrap();
+    assert_eq!(report.checksums_recorded, 1);
+    assert_eq!(report.corrupt_blocks, Vec::<u64>::new());
+
+    // The recorded checksum is persisted and covers the block from now on.
+    let mut image = disk.into_backend().into_vec();
+    flip_byte_in_slot(&mut image, 2, 8);
+    let mut disk = AeroSparseDisk::open(MemBackend::from_vec(image)).unwrap();
+    assert!(matches!(
+        disk.read_at(3 * BLOCK_SIZE, &mut [0u8; 1]),
+        Err(DiskError::ChecksumMismatch { block_idx: 3, .. })
+    ));
+}
+
+#[test]
+fn discard_and_resize_keep_checksums_consistent() {
+    // 250 entries fit in the first 4 KiB block twice over (allocation + checksum table); 600
+    // entries push the data region to 12 KiB, so the blocks in slots 0 and 1 move.
+    let mut disk = create(250, true);
+    assert_eq!(disk.header().data_offset, BLOCK_SIZE);
+    let mut expected = vec![0u8; (600 * BLOCK_SIZE) as usize];
+    for (i, block) in [5u64, 7, 249].into_iter().enumerate() {
+        let off = block * BLOCK_SIZE + 64;
+        disk.write_at(off, &[i as u8 + 1; 128]).unwrap();
+        expected[off as usize..off as usize + 128].fill(i as u8 + 1);
+    }
+    disk.discard_range(7 * BLOCK_SIZE, BLOCK_SIZE).unwrap();
+    expected[(7 * BLOCK_SIZE) as usize..(8 * BLOCK_SIZE) as usize].fill(0);
+    disk.write_at(7 * BLOCK_SIZE, &[0x99; 16]).unwrap();
+    expected[(7 * BLOCK_SIZE) as usize..(7 * BLOCK_SIZE) as usize + 16].fill(0x99);
+
+    disk.resize(600 * BLOCK_SIZE).unwrap();
+    assert!(disk.header().data_offset > BLOCK_SIZE);
+    disk.write_at(599 * BLOCK_SIZE + 1, &[0x77; 3]).unwrap();
+    expected[(599 * BLOCK_SIZE + 1) as usize..(599 * BLOCK_SIZE + 4) as usize].fill(0x77);
+    assert_eq!(read_all(&mut disk), expected);
+
+    let mut reopened = AeroSparseDisk::open(disk.into_backend()).unwrap();
+    assert_eq!(read_all(&mut reopened), expected);
+    let report = reopened.scrub(|_, _| true).unwrap();
+    assert_eq!(report.blocks_checked, 4);
+    assert_eq!(report.checksums_recorded, 0);
+    assert_eq!(report.corrupt_blocks, Vec::<u64>::new());
+}
@@ -10,6 +10,7 @@ fn make_disk() -> AeroSparseDisk<MemBackend> {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: BLOCK_SIZE,
+            ..Default::default()
         },
     )
     .unwrap()
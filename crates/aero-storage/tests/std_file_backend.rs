@@ -152,12 +152,16 @@ fn std_file_backend_open_missing_file_reports_path_in_error() {
 
     let err = StdFileBackend::open_read_only(&path).unwrap_err();
     match err {
-        DiskError::Io(msg) => {
+        DiskError::NativeIo {
+            message: msg,
+            source,
+        } => {
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
             assert!(msg.contains("failed to open file"));
             assert!(msg.contains(&format!("path={}", path.display())));
             assert!(msg.contains("read_only=true"));
         }
-        other => panic!("expected DiskError::Io, got {other:?}"),
+        other => panic!("expected DiskError::NativeIo, got {other:?}"),
     }
 }
 
@@ -169,12 +173,12 @@ fn std_file_backend_create_missing_parent_reports_path_and_size() {
 
     let err = StdFileBackend::create(&path, size).unwrap_err();
     match err {
-        DiskError::Io(msg) => {
+        DiskError::NativeIo { message: msg, .. } => {
             assert!(msg.contains("failed to create file"));
             assert!(msg.contains(&format!("path={}", path.display())));
             assert!(msg.contains(&format!("size={size}")));
         }
-        other => panic!("expected DiskError::Io, got {other:?}"),
+        other => panic!("expected DiskError::NativeIo, got {other:?}"),
     }
 }
 
@@ -189,13 +193,13 @@ fn std_file_backend_from_file_with_path_reports_path_in_io_errors() {
 
     let err = backend.write_at(0, b"x").unwrap_err();
     match err {
-        DiskError::Io(msg) => {
+        DiskError::NativeIo { message: msg, .. } => {
             assert!(msg.contains("write_at failed"));
             assert!(msg.contains(&format!("path={}", tmp.path().display())));
             assert!(msg.contains("offset=0"));
             assert!(msg.contains("len=1"));
         }
-        other => panic!("expected DiskError::Io, got {other:?}"),
+        other => panic!("expected DiskError::NativeIo, got {other:?}"),
     }
 }
 
@@ -210,12 +214,12 @@ fn std_file_backend_from_file_reports_unknown_path_in_io_errors() {
 
     let err = backend.write_at(0, b"x").unwrap_err();
     match err {
-        DiskError::Io(msg) => {
+        DiskError::NativeIo { message: msg, .. } => {
             assert!(msg.contains("write_at failed"));
             assert!(msg.contains("path=<unknown>"));
             assert!(msg.contains("offset=0"));
             assert!(msg.contains("len=1"));
         }
-        other => panic!("expected DiskError::Io, got {other:?}"),
+        other => panic!("expected DiskError::NativeIo, got {other:?}"),
     }
 }
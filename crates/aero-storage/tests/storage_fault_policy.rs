@@ -0,0 +1,99 @@
+use aero_storage::{
+    DiskError, StorageFaultDelay, StorageFaultError, StorageFaultInjector, StorageFaultPolicy,
+    StorageRequest, StorageRequestKind,
+};
+
+fn read(lba: u64, sectors: u64) -> StorageRequest {
+    StorageRequest {
+        kind: StorageRequestKind::Read,
+        lba,
+        sectors,
+    }
+}
+
+#[test]
+fn default_policy_injects_nothing() {
+    let mut injector = StorageFaultInjector::new(StorageFaultPolicy::default());
+    for lba in 0..10 {
+        let outcome = injector.on_request(read(lba, 8));
+        assert_eq!(outcome.delay_ns, 0);
+        assert_eq!(outcome.error, None);
+    }
+    let counters = injector.counters();
+    assert_eq!(counters.requests, 10);
+    assert_eq!(counters.delayed, 0);
+    assert_eq!(counters.failed_nth + counters.failed_lba_range, 0);
+}
+
+#[test]
+fn nth_and_lba_range_failures_are_counted_separately() {
+    let mut injector = StorageFaultInjector::new(StorageFaultPolicy {
+        fail_every_nth: Some((4, StorageFaultError::QuotaExceeded)),
+        fail_lba_range: Some((100..108, StorageFaultError::Io)),
+        ..Default::default()
+    });
+
+    let errors: Vec<_> = [0, 96, 107, 50, 108, 99]
+        .into_iter()
+        .map(|lba| injector.on_request(read(lba, 4)).error)
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            None,
+            None,
+            Some(StorageFaultError::Io),
+            // The 4th request hits the nth rule.
+            Some(StorageFaultError::QuotaExceeded),
+            None,
+            // 99..103 overlaps the range.
+            Some(StorageFaultError::Io),
+        ]
+    );
+    let counters = injector.counters();
+    assert_eq!(counters.failed_nth, 1);
+    assert_eq!(counters.failed_lba_range, 2);
+
+    // Flushes address no sectors, so only the nth rule applies to them.
+    let flush = StorageRequest {
+        kind: StorageRequestKind::Flush,
+        lba: 0,
+        sectors: 0,
+    };
+    assert_eq!(injector.on_request(flush).error, None);
+    assert!(matches!(
+        StorageFaultError::BackendUnavailable.to_disk_error(),
+        DiskError::BackendUnavailable
+    ));
+}
+
+#[test]
+fn uniform_delays_are_bounded_and_reproducible_from_the_seed() {
+    let policy = |seed| StorageFaultPolicy {
+        delay: StorageFaultDelay::Uniform {
+            min_ns: 1_000,
+            max_ns: 9_000,
+        },
+        seed,
+        ..Default::default()
+    };
+    let delays = |seed| {
+        let mut injector = StorageFaultInjector::new(policy(seed));
+        (0..64)
+            .map(|lba| injector.on_request(read(lba, 1)).delay_ns)
+            .collect::<Vec<_>>()
+    };
+
+    let first = delays(7);
+    assert!(first.iter().all(|d| (1_000..=9_000).contains(d)));
+    assert_eq!(first, delays(7));
+    assert_ne!(first, delays(8));
+
+    let mut injector = StorageFaultInjector::new(policy(7));
+    for lba in 0..64 {
+        injector.on_request(read(lba, 1));
+    }
+    let counters = injector.counters();
+    assert_eq!(counters.delayed, 64);
+    assert_eq!(counters.delay_ns_total, first.iter().sum::<u64>());
+}
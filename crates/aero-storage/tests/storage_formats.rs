@@ -429,6 +429,7 @@ fn detect_aerosparse_and_raw() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -449,6 +450,7 @@ fn open_auto_works_for_aerosparse_and_raw() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -467,6 +469,7 @@ fn aerosparse_rejects_table_entry_before_data_region() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -501,6 +504,7 @@ fn aerosparse_rejects_misaligned_table_entry() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -535,6 +539,7 @@ fn aerosparse_rejects_table_entry_pointing_past_allocated_region() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -571,6 +576,7 @@ fn aerosparse_rejects_absurd_allocation_table_sizes() {
 
     let header = AeroSparseHeader {
         version: 1,
+        flags: 0,
         block_size_bytes,
         disk_size_bytes,
         table_entries,
@@ -598,6 +604,7 @@ fn aerosparse_rejects_table_entries_mismatch() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -625,6 +632,7 @@ fn aerosparse_rejects_allocated_blocks_exceeding_table_entries() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -656,6 +664,7 @@ fn aerosparse_create_rejects_absurd_allocation_table_sizes() {
         AeroSparseConfig {
             disk_size_bytes,
             block_size_bytes,
+            ..Default::default()
         },
     ) {
         Ok(_) => panic!("expected create to fail"),
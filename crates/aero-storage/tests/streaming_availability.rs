@@ -0,0 +1,291 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_storage::{
+    DiskError, StreamingAvailabilityPolicy, StreamingDisk, StreamingDiskConfig, StreamingDiskError,
+};
+use hyper::header::{ACCEPT_RANGES, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use tokio::sync::oneshot;
+use url::Url;
+
+const CHUNK_SIZE: u64 = 1024;
+const ETAG: &str = "\"availability-v1\"";
+
+struct State {
+    image: Vec<u8>,
+    /// Range requests starting at or beyond this offset fail with 503.
+    fail_from: AtomicU64,
+    range_requests: AtomicUsize,
+}
+
+impl State {
+    fn fail_from(&self, offset: u64) {
+        self.fail_from.store(offset, Ordering::SeqCst);
+    }
+
+    fn recover(&self) {
+        self.fail_from.store(u64::MAX, Ordering::SeqCst);
+    }
+}
+
+async fn start_server(image: Vec<u8>) -> (Url, Arc<State>, oneshot::Sender<()>) {
+    let state = Arc::new(State {
+        image,
+        fail_from: AtomicU64::new(u64::MAX),
+        range_requests: AtomicUsize::new(0),
+    });
+
+    let make_svc = {
+        let state = state.clone();
+        make_service_fn(move |_conn| {
+            let state = state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle_request(req, state.clone()))) }
+        })
+    };
+
+    let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
+    let builder = Server::try_bind(&addr).expect("bind");
+    let local_addr = builder.local_addr();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = builder.serve(make_svc).with_graceful_shutdown(async move {
+        let _ = shutdown_rx.await;
+    });
+    tokio::spawn(server);
+
+    let url = Url::parse(&format!("http://{local_addr}/image.raw")).expect("url");
+    (url, state, shutdown_tx)
+}
+
+async fn handle_request(
+    req: Request<Body>,
+    state: Arc<State>,
+) -> Result<Response<Body>, Infallible> {
+    let total = state.image.len() as u64;
+    let mut resp = Response::new(Body::empty());
+    match (req.method(), req.headers().get(RANGE)) {
+        (&Method::HEAD, _) => {
+            resp.headers_mut()
+                .insert(CONTENT_LENGTH, total.to_string().parse().unwrap());
+            resp.headers_mut()
+                .insert(ACCEPT_RANGES, "bytes".parse().unwrap());
+            resp.headers_mut()
+                .insert(hyper::header::ETAG, ETAG.parse().unwrap());
+        }
+        (&Method::GET, Some(range)) => {
+            state.range_requests.fetch_add(1, Ordering::SeqCst);
+            let (start, end_inclusive) = range
+                .to_str()
+                .ok()
+                .and_then(|r| r.strip_prefix("bytes="))
+                .and_then(|r| r.split_once('-'))
+                .and_then(|(a, b)| Some((a.parse::<u64>().ok()?, b.parse::<u64>().ok()?)))
+                .expect("single byte range");
+            let end_inclusive = end_inclusive.min(total - 1);
+            if start >= state.fail_from.load(Ordering::SeqCst) {
+                *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                return Ok(resp);
+            }
+
+            let body = state.image[start as usize..=end_inclusive as usize].to_vec();
+            let len = body.len();
+            *resp.body_mut() = Body::from(body);
+            *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+            let headers = resp.headers_mut();
+            headers.insert(CONTENT_LENGTH, len.to_string().parse().unwrap());
+            headers.insert(CACHE_CONTROL, "no-transform".parse().unwrap());
+            headers.insert(ACCEPT_RANGES, "bytes".parse().unwrap());
+            headers.insert(hyper::header::ETAG, ETAG.parse().unwrap());
+            headers.insert(
+                CONTENT_RANGE,
+                format!("bytes {start}-{end_inclusive}/{total}")
+                    .parse()
+                    .unwrap(),
+            );
+        }
+        _ => *resp.status_mut() = StatusCode::METHOD_NOT_ALLOWED,
+    }
+    Ok(resp)
+}
+
+fn image() -> Vec<u8> {
+    (0..4 * CHUNK_SIZE).map(|i| (i % 251) as u8).collect()
+}
+
+async fn open_disk(
+    url: Url,
+    policy: StreamingAvailabilityPolicy,
+) -> (StreamingDisk, tempfile::TempDir) {
+    let cache_dir = tempdir().unwrap();
+    let mut config = StreamingDiskConfig::new(url, cache_dir.path());
+    config.options.chunk_size = CHUNK_SIZE;
+    config.options.read_ahead_chunks = 0;
+    config.options.max_retries = 2;
+    config.availability = policy;
+    let disk = StreamingDisk::open(config).await.unwrap();
+    (disk, cache_dir)
+}
+
+/// Caches chunk 0, then makes chunk 1 (and beyond) fail so a read spanning both fails mid-read.
+async fn prime_then_fail(disk: &StreamingDisk, state: &State) {
+    let mut buf = vec![0u8; CHUNK_SIZE as usize];
+    disk.read_at(0, &mut buf).await.unwrap();
+    state.fail_from(CHUNK_SIZE);
+}
+
+const SPAN_OFFSET: u64 = CHUNK_SIZE / 2;
+const SPAN_LEN: usize = CHUNK_SIZE as usize;
+
+fn expected_span() -> Vec<u8> {
+    image()[SPAN_OFFSET as usize..SPAN_OFFSET as usize + SPAN_LEN].to_vec()
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn fail_fast_surfaces_mid_read_fetch_failure_after_max_retries() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let (disk, _cache_dir) = open_disk(url, StreamingAvailabilityPolicy::FailFast).await;
+    prime_then_fail(&disk, &state).await;
+
+    let before = state.range_requests.load(Ordering::SeqCst);
+    let mut buf = vec![0u8; SPAN_LEN];
+    let err = disk.read_at(SPAN_OFFSET, &mut buf).await.unwrap_err();
+    assert!(matches!(
+        err,
+        StreamingDiskError::HttpStatus { status: 503 }
+    ));
+    assert_eq!(state.range_requests.load(Ordering::SeqCst) - before, 2);
+    assert_eq!(disk.cached_fraction().await, 0.25);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_backoff_rides_out_a_short_outage() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let policy = StreamingAvailabilityPolicy::RetryWithBackoff {
+        max_wait: Duration::from_secs(30),
+    };
+    let (disk, _cache_dir) = open_disk(url, policy).await;
+    prime_then_fail(&disk, &state).await;
+
+    let mut buf = vec![0u8; SPAN_LEN];
+    let (res, ()) = tokio::join!(disk.read_at(SPAN_OFFSET, &mut buf), async {
+        // Outlast FailFast's retry budget before the server comes back.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        state.recover();
+    });
+    res.unwrap();
+    assert_eq!(buf, expected_span());
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn retry_with_backoff_gives_up_after_max_wait() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let max_wait = Duration::from_millis(400);
+    let (disk, _cache_dir) = open_disk(
+        url,
+        StreamingAvailabilityPolicy::RetryWithBackoff { max_wait },
+    )
+    .await;
+    prime_then_fail(&disk, &state).await;
+
+    let started = Instant::now();
+    let mut buf = vec![0u8; SPAN_LEN];
+    let err = disk.read_at(SPAN_OFFSET, &mut buf).await.unwrap_err();
+    assert!(matches!(
+        err,
+        StreamingDiskError::HttpStatus { status: 503 }
+    ));
+    let elapsed = started.elapsed();
+    assert!(elapsed >= max_wait, "gave up after {elapsed:?}");
+    assert!(
+        elapsed < Duration::from_secs(5),
+        "gave up after {elapsed:?}"
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn block_until_available_waits_for_the_server() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let (disk, _cache_dir) = open_disk(url, StreamingAvailabilityPolicy::BlockUntilAvailable).await;
+    prime_then_fail(&disk, &state).await;
+
+    let mut buf = vec![0u8; SPAN_LEN];
+    let (res, ()) = tokio::join!(disk.read_at(SPAN_OFFSET, &mut buf), async {
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        // Still pending well past the FailFast retry budget.
+        assert!(state.range_requests.load(Ordering::SeqCst) > 3);
+        state.recover();
+    });
+    res.unwrap();
+    assert_eq!(buf, expected_span());
+    assert_eq!(disk.cached_fraction().await, 0.5);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn going_offline_aborts_a_blocked_read() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let (disk, _cache_dir) = open_disk(url, StreamingAvailabilityPolicy::BlockUntilAvailable).await;
+    prime_then_fail(&disk, &state).await;
+
+    let mut buf = vec![0u8; SPAN_LEN];
+    let (res, ()) = tokio::join!(disk.read_at(SPAN_OFFSET, &mut buf), async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        disk.set_offline(true);
+    });
+    assert!(matches!(
+        res,
+        Err(StreamingDiskError::Offline { chunk_index: 1 })
+    ));
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn offline_serves_cached_chunks_and_fails_uncached_ones_without_fetching() {
+    let (url, state, shutdown) = start_server(image()).await;
+    let (disk, _cache_dir) = open_disk(url, StreamingAvailabilityPolicy::FailFast).await;
+    assert_eq!(disk.cached_fraction().await, 0.0);
+
+    let mut buf = vec![0u8; CHUNK_SIZE as usize];
+    disk.read_at(2 * CHUNK_SIZE, &mut buf).await.unwrap();
+    assert_eq!(disk.cached_fraction().await, 0.25);
+
+    disk.set_offline(true);
+    assert!(disk.is_offline());
+    let before = state.range_requests.load(Ordering::SeqCst);
+
+    let mut cached = vec![0u8; 16];
+    disk.read_at(2 * CHUNK_SIZE + 8, &mut cached).await.unwrap();
+    let start = (2 * CHUNK_SIZE + 8) as usize;
+    assert_eq!(cached, image()[start..start + 16]);
+
+    let err = disk.read_at(0, &mut buf).await.unwrap_err();
+    assert!(matches!(
+        err,
+        StreamingDiskError::Offline { chunk_index: 0 }
+    ));
+    assert_eq!(state.range_requests.load(Ordering::SeqCst), before);
+    let err = DiskError::from(err);
+    assert!(matches!(err.root(), DiskError::BackendUnavailable));
+    assert_eq!(err.to_string(), "streaming[chunk=0]: backend unavailable");
+
+    disk.set_offline(false);
+    disk.read_at(0, &mut buf).await.unwrap();
+    assert_eq!(buf, image()[..CHUNK_SIZE as usize]);
+    assert_eq!(disk.cached_fraction().await, 0.5);
+
+    let _ = shutdown.send(());
+}
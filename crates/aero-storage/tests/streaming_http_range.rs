@@ -1,7 +1,8 @@
 #![cfg(not(target_arch = "wasm32"))]
 
 use aero_storage::{
-    ChunkManifest, StreamingCacheBackend, StreamingDisk, StreamingDiskConfig, StreamingDiskError,
+    ChunkHashAlgorithm, ChunkManifest, StreamingCacheBackend, StreamingDisk, StreamingDiskConfig,
+    StreamingDiskError,
 };
 use hyper::header::{
     ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, IF_RANGE,
@@ -13,8 +14,9 @@ use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tempfile::tempdir;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, Notify};
 use url::Url;
 
 #[derive(Default)]
@@ -22,6 +24,8 @@ struct Counters {
     head: AtomicUsize,
     get_range: AtomicUsize,
     get_full: AtomicUsize,
+    range_in_flight: AtomicUsize,
+    max_range_in_flight: AtomicUsize,
 }
 
 struct State {
@@ -35,6 +39,9 @@ struct State {
     wrong_content_range: bool,
     content_range_total_star: bool,
     content_encoding: Option<String>,
+    /// Hold range responses until this many range requests have arrived.
+    range_gate: Option<usize>,
+    range_gate_open: Notify,
     counters: Counters,
 }
 
@@ -55,6 +62,7 @@ struct RangeServerOptions<'a> {
     wrong_content_range: bool,
     content_range_total_star: bool,
     content_encoding: Option<&'a str>,
+    range_gate: Option<usize>,
 }
 
 impl<'a> RangeServerOptions<'a> {
@@ -69,6 +77,7 @@ impl<'a> RangeServerOptions<'a> {
             wrong_content_range: false,
             content_range_total_star: false,
             content_encoding: None,
+            range_gate: None,
         }
     }
 }
@@ -90,6 +99,8 @@ async fn start_range_server_with_options(
         wrong_content_range: options.wrong_content_range,
         content_range_total_star: options.content_range_total_star,
         content_encoding: options.content_encoding.map(|v| v.to_string()),
+        range_gate: options.range_gate,
+        range_gate_open: Notify::new(),
         counters: Counters::default(),
     });
 
@@ -152,7 +163,36 @@ async fn handle_request(
         }
         Method::GET => {
             if let Some(range_header) = req.headers().get(RANGE).and_then(|v| v.to_str().ok()) {
-                state.counters.get_range.fetch_add(1, Ordering::SeqCst);
+                let arrived = state.counters.get_range.fetch_add(1, Ordering::SeqCst) + 1;
+
+                if let Some(gate) = state.range_gate {
+                    let in_flight = state
+                        .counters
+                        .range_in_flight
+                        .fetch_add(1, Ordering::SeqCst)
+                        + 1;
+                    state
+                        .counters
+                        .max_range_in_flight
+                        .fetch_max(in_flight, Ordering::SeqCst);
+                    if arrived >= gate {
+                        state.range_gate_open.notify_waiters();
+                    } else {
+                        loop {
+                            // `notified()` registers before the check, so a concurrent
+                            // `notify_waiters` cannot be missed.
+                            let opened = state.range_gate_open.notified();
+                            if state.counters.get_range.load(Ordering::SeqCst) >= gate {
+                                break;
+                            }
+                            opened.await;
+                        }
+                    }
+                    state
+                        .counters
+                        .range_in_flight
+                        .fetch_sub(1, Ordering::SeqCst);
+                }
 
                 let current_etag = state.etag.lock().unwrap().clone();
                 if let Some(if_range) = req.headers().get(IF_RANGE).and_then(|v| v.to_str().ok()) {
@@ -555,6 +595,150 @@ async fn inflight_dedup_avoids_duplicate_fetches() {
     let _ = shutdown.send(());
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn multi_chunk_read_fetches_chunks_concurrently() {
+    let image: Vec<u8> = (0..8 * 1024).map(|i| (i % 251) as u8).collect();
+    let (url, state, shutdown) = start_range_server_with_options(
+        image.clone(),
+        RangeServerOptions {
+            fail_first_range: true,
+            // The first three range requests are only answered once all three have arrived, so
+            // the read completes only if it keeps three fetches in flight.
+            range_gate: Some(3),
+            ..RangeServerOptions::new("etag-parallel")
+        },
+    )
+    .await;
+
+    let cache_dir = tempdir().unwrap();
+    let mut config = StreamingDiskConfig::new(url, cache_dir.path());
+    config.cache_backend = StreamingCacheBackend::SparseFile;
+    config.options.chunk_size = 1024;
+    config.options.read_ahead_chunks = 0;
+    config.options.max_retries = 2;
+    config.options.max_concurrent_fetches = 3;
+
+    let disk = StreamingDisk::open(config).await.unwrap();
+    let mut buf = vec![0u8; image.len() - 100];
+    // A read that never reaches three concurrent fetches would wait on the gate forever.
+    tokio::time::timeout(Duration::from_secs(30), disk.read_at(50, &mut buf))
+        .await
+        .expect("read should not stall on the range gate")
+        .unwrap();
+    assert_eq!(&buf[..], &image[50..image.len() - 50]);
+
+    // All 8 chunks are fetched once; the first request fails and is retried.
+    assert_eq!(state.counters.get_range.load(Ordering::SeqCst), 9);
+    assert_eq!(state.counters.max_range_in_flight.load(Ordering::SeqCst), 3);
+
+    let telemetry = disk.telemetry_snapshot();
+    assert_eq!(telemetry.cache_miss_chunks, 8);
+    assert_eq!(telemetry.bytes_downloaded, 8 * 1024);
+    assert_eq!(telemetry.max_in_flight_fetches, 3);
+    assert!(telemetry.aggregate_bandwidth_bytes_per_sec > 0);
+
+    // Every chunk was recorded as downloaded.
+    let status = disk.cache_status().await;
+    assert_eq!(status.cached_bytes, image.len() as u64);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn verify_on_read_refetches_corrupted_cache_chunk() {
+    let image: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+    let (url, state, shutdown) =
+        start_range_server_with_options(image.clone(), RangeServerOptions::new("etag-verify"))
+            .await;
+
+    let cache_dir = tempdir().unwrap();
+    let mut config = StreamingDiskConfig::new(url, cache_dir.path());
+    config.cache_backend = StreamingCacheBackend::SparseFile;
+    config.options.chunk_size = 1024;
+    config.options.read_ahead_chunks = 0;
+    config.options.chunk_hash = ChunkHashAlgorithm::Sha256;
+    config.options.verify_on_read = true;
+
+    let disk = StreamingDisk::open(config.clone()).await.unwrap();
+    let mut buf = vec![0u8; 1024];
+    disk.read_at(1024, &mut buf).await.unwrap();
+    assert_eq!(state.counters.get_range.load(Ordering::SeqCst), 1);
+    drop(disk);
+
+    // Flip a byte of the cached chunk behind the disk's back.
+    {
+        use std::io::{Seek, SeekFrom, Write};
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(cache_dir.path().join("cache.bin"))
+            .unwrap();
+        file.seek(SeekFrom::Start(1024 + 17)).unwrap();
+        file.write_all(&[image[1024 + 17] ^ 0xFF]).unwrap();
+    }
+
+    let disk = StreamingDisk::open(config).await.unwrap();
+    let mut buf = vec![0u8; 1024];
+    disk.read_at(1024, &mut buf).await.unwrap();
+    assert_eq!(&buf[..], &image[1024..2048]);
+    assert_eq!(
+        state.counters.get_range.load(Ordering::SeqCst),
+        2,
+        "corrupted chunk should be re-fetched"
+    );
+    let telemetry = disk.telemetry_snapshot();
+    assert_eq!(telemetry.corrupt_chunks, 1);
+    assert!(telemetry.chunk_hash_nanos > 0);
+
+    // The re-fetched chunk verifies cleanly.
+    disk.read_at(1024, &mut buf).await.unwrap();
+    assert_eq!(state.counters.get_range.load(Ordering::SeqCst), 2);
+    assert_eq!(disk.telemetry_snapshot().corrupt_chunks, 1);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn verify_all_evicts_corrupted_chunks() {
+    let image: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+    let (url, state, shutdown) =
+        start_range_server_with_options(image.clone(), RangeServerOptions::new("etag-scan")).await;
+
+    let cache_dir = tempdir().unwrap();
+    let mut config = StreamingDiskConfig::new(url, cache_dir.path());
+    config.cache_backend = StreamingCacheBackend::Directory;
+    config.options.chunk_size = 1024;
+    config.options.read_ahead_chunks = 0;
+
+    let disk = StreamingDisk::open(config).await.unwrap();
+    let mut buf = vec![0u8; image.len()];
+    disk.read_at(0, &mut buf).await.unwrap();
+    assert_eq!(state.counters.get_range.load(Ordering::SeqCst), 4);
+
+    let report = disk.verify_all().await.unwrap();
+    assert_eq!(report.checked_chunks, 4);
+    assert_eq!(report.unhashed_chunks, 0);
+    assert!(report.corrupt_chunks.is_empty());
+
+    // Corrupt chunk 2 (same length, different contents); verification is off for reads, so only
+    // the scan notices.
+    let chunk_path = cache_dir.path().join("chunks").join("2.bin");
+    let mut corrupted = std::fs::read(&chunk_path).unwrap();
+    corrupted[0] ^= 0xFF;
+    std::fs::write(&chunk_path, &corrupted).unwrap();
+
+    let report = disk.verify_all().await.unwrap();
+    assert_eq!(report.checked_chunks, 4);
+    assert_eq!(report.corrupt_chunks, vec![2]);
+    assert_eq!(disk.telemetry_snapshot().corrupt_chunks, 1);
+    assert_eq!(disk.cache_status().await.cached_bytes, 3 * 1024);
+
+    disk.read_at(0, &mut buf).await.unwrap();
+    assert_eq!(&buf[..], &image[..]);
+    assert_eq!(state.counters.get_range.load(Ordering::SeqCst), 5);
+
+    let _ = shutdown.send(());
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn retries_transient_http_errors() {
     let image: Vec<u8> = (0..2048).map(|i| (i % 251) as u8).collect();
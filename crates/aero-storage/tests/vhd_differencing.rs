@@ -1,6 +1,6 @@
 use aero_storage::{
-    DiskFormat, DiskImage, MemBackend, RawDisk, StorageBackend as _, VhdDisk, VirtualDisk,
-    SECTOR_SIZE,
+    DiskError, DiskFormat, DiskImage, MemBackend, RawDisk, StorageBackend as _,
+    VhdDifferencingOptions, VhdDisk, VhdIdentity, VirtualDisk, SECTOR_SIZE,
 };
 use std::sync::{
     atomic::{AtomicU64, Ordering},
@@ -52,6 +52,10 @@ fn make_vhd_footer(virtual_size: u64, disk_type: u32, data_offset: u64) -> [u8;
 }
 
 fn make_vhd_differencing_empty(virtual_size: u64, block_size: u32) -> MemBackend {
+    make_vhd_sparse_empty(virtual_size, block_size, 4)
+}
+
+fn make_vhd_sparse_empty(virtual_size: u64, block_size: u32, disk_type: u32) -> MemBackend {
     assert_eq!(virtual_size % SECTOR_SIZE as u64, 0);
     assert_eq!(block_size as usize % SECTOR_SIZE, 0);
 
@@ -62,7 +66,7 @@ fn make_vhd_differencing_empty(virtual_size: u64, block_size: u32) -> MemBackend
     let bat_bytes = max_table_entries as u64 * 4;
     let bat_size = bat_bytes.div_ceil(SECTOR_SIZE as u64) * SECTOR_SIZE as u64;
 
-    let footer = make_vhd_footer(virtual_size, 4, dyn_header_offset);
+    let footer = make_vhd_footer(virtual_size, disk_type, dyn_header_offset);
     let file_len = (SECTOR_SIZE as u64) + 1024 + bat_size + (SECTOR_SIZE as u64);
     let mut backend = MemBackend::with_len(file_len).unwrap();
 
@@ -90,6 +94,88 @@ fn make_vhd_differencing_empty(virtual_size: u64, block_size: u32) -> MemBackend
     backend
 }
 
+/// Stamp a unique id + timestamp into both footer copies of an image without allocated blocks.
+fn set_footer_identity(backend: &mut MemBackend, identity: VhdIdentity) {
+    let len = backend.len().unwrap();
+    let mut footer = [0u8; SECTOR_SIZE];
+    backend.read_at(0, &mut footer).unwrap();
+    write_be_u32(&mut footer, 24, identity.timestamp);
+    footer[68..84].copy_from_slice(&identity.unique_id);
+    write_be_u32(&mut footer, 64, 0);
+    let checksum = vhd_footer_checksum(&footer);
+    write_be_u32(&mut footer, 64, checksum);
+    backend.write_at(0, &footer).unwrap();
+    backend.write_at(len - SECTOR_SIZE as u64, &footer).unwrap();
+}
+
+/// Record parent metadata in an empty differencing image, appending locator payloads before the
+/// EOF footer.
+fn set_parent_metadata(
+    backend: &mut MemBackend,
+    parent: VhdIdentity,
+    name: &str,
+    locators: &[(&[u8; 4], Vec<u8>)],
+) {
+    let mut footer = [0u8; SECTOR_SIZE];
+    backend.read_at(0, &mut footer).unwrap();
+    let dyn_header_offset = SECTOR_SIZE as u64;
+    let mut dyn_header = [0u8; 1024];
+    backend.read_at(dyn_header_offset, &mut dyn_header).unwrap();
+
+    dyn_header[40..56].copy_from_slice(&parent.unique_id);
+    write_be_u32(&mut dyn_header, 56, parent.timestamp);
+    for (i, unit) in name.encode_utf16().enumerate() {
+        dyn_header[64 + i * 2..66 + i * 2].copy_from_slice(&unit.to_be_bytes());
+    }
+
+    let mut data_offset = backend.len().unwrap() - SECTOR_SIZE as u64;
+    for (i, (code, data)) in locators.iter().enumerate() {
+        let space = data.len().div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+        backend.set_len(data_offset + space as u64).unwrap();
+        backend.write_at(data_offset, data).unwrap();
+
+        let entry = 576 + i * 24;
+        dyn_header[entry..entry + 4].copy_from_slice(*code);
+        write_be_u32(&mut dyn_header, entry + 4, (space / SECTOR_SIZE) as u32);
+        write_be_u32(&mut dyn_header, entry + 8, data.len() as u32);
+        write_be_u64(&mut dyn_header, entry + 16, data_offset);
+        data_offset += space as u64;
+    }
+    backend.set_len(data_offset + SECTOR_SIZE as u64).unwrap();
+    backend.write_at(data_offset, &footer).unwrap();
+
+    write_be_u32(&mut dyn_header, 36, 0);
+    let checksum = vhd_dynamic_header_checksum(&dyn_header);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    backend.write_at(dyn_header_offset, &dyn_header).unwrap();
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+}
+
+const PARENT_IDENTITY: VhdIdentity = VhdIdentity {
+    unique_id: [0x5A; 16],
+    timestamp: 0x2A00_0000,
+};
+
+/// A dynamic VHD parent stamped with `PARENT_IDENTITY`, plus a differencing child pointing at it.
+fn make_parent_and_child(virtual_size: u64, block_size: u32) -> (MemBackend, MemBackend) {
+    let mut parent = make_vhd_sparse_empty(virtual_size, block_size, 3);
+    set_footer_identity(&mut parent, PARENT_IDENTITY);
+    let mut child = make_vhd_differencing_empty(virtual_size, block_size);
+    set_parent_metadata(
+        &mut child,
+        PARENT_IDENTITY,
+        "base.vhd",
+        &[
+            (b"W2ku", utf16le("C:\\images\\base.vhd")),
+            (b"W2ru", utf16le(".\\base.vhd")),
+        ],
+    );
+    (parent, child)
+}
+
 struct CountingDisk<D> {
     inner: D,
     reads: Arc<AtomicU64>,
@@ -250,3 +336,144 @@ fn disk_image_open_auto_with_parent_supports_vhd_differencing() {
     disk.read_at(start as u64, &mut buf).unwrap();
     assert_eq!(&buf, &pattern[start..start + buf.len()]);
 }
+
+#[test]
+fn vhd_differencing_parent_info_exposes_identity_and_locators() {
+    let (_parent, mut child) = make_parent_and_child(16 * 1024, 4096);
+
+    let info = VhdDisk::read_parent_info(&mut child).unwrap();
+    assert_eq!(info.identity, PARENT_IDENTITY);
+    assert_eq!(info.name, "base.vhd");
+    assert_eq!(info.locators.len(), 2);
+    assert_eq!(&info.locators[0].platform_code, b"W2ku");
+    assert_eq!(
+        info.locators[0].path.as_deref(),
+        Some("C:\\images\\base.vhd")
+    );
+    assert_eq!(info.preferred_path(), Some(".\\base.vhd"));
+
+    let base = RawDisk::create(MemBackend::new(), 16 * 1024).unwrap();
+    let disk = VhdDisk::open_differencing(child, Box::new(base)).unwrap();
+    assert_eq!(disk.parent_info(), Some(&info));
+
+    // Non-differencing images have no parent metadata.
+    let mut dynamic = make_vhd_sparse_empty(16 * 1024, 4096, 3);
+    assert!(matches!(
+        VhdDisk::read_parent_info(&mut dynamic),
+        Err(DiskError::InvalidConfig(_))
+    ));
+    assert!(VhdDisk::open(dynamic).unwrap().parent_info().is_none());
+}
+
+#[test]
+fn vhd_differencing_validates_parent_identity() {
+    let (parent, child) = make_parent_and_child(16 * 1024, 4096);
+    let parent = VhdDisk::open(parent).unwrap();
+    assert_eq!(parent.identity(), PARENT_IDENTITY);
+
+    let open = |identity: VhdIdentity, check_timestamp: bool| {
+        VhdDisk::open_differencing_with_options(
+            child.clone(),
+            Box::new(RawDisk::create(MemBackend::new(), 16 * 1024).unwrap()),
+            VhdDifferencingOptions {
+                parent_identity: Some(identity),
+                check_timestamp,
+            },
+        )
+    };
+
+    assert!(open(PARENT_IDENTITY, true).is_ok());
+
+    let wrong_id = VhdIdentity {
+        unique_id: [0x11; 16],
+        ..PARENT_IDENTITY
+    };
+    assert!(matches!(
+        open(wrong_id, false),
+        Err(DiskError::InvalidConfig(_))
+    ));
+
+    // Copies of the parent frequently carry a different timestamp.
+    let touched = VhdIdentity {
+        timestamp: PARENT_IDENTITY.timestamp + 60,
+        ..PARENT_IDENTITY
+    };
+    assert!(matches!(
+        open(touched, true),
+        Err(DiskError::InvalidConfig(_))
+    ));
+    assert!(open(touched, false).is_ok());
+}
+
+#[test]
+fn vhd_differencing_rejects_parent_capacity_mismatch() {
+    let child = make_vhd_differencing_empty(16 * 1024, 4096);
+    let parent = RawDisk::create(MemBackend::new(), 32 * 1024).unwrap();
+    let err = VhdDisk::open_differencing(child, Box::new(parent))
+        .err()
+        .expect("capacity mismatch should fail");
+    assert!(
+        matches!(err, DiskError::InvalidConfig(msg) if msg.contains("capacity")),
+        "unexpected error: {err:?}"
+    );
+}
+
+#[test]
+fn vhd_differencing_chain_round_trip_with_writes_straddling_bitmap_boundaries() {
+    let virtual_size = 32 * 1024u64;
+    let block_size = 4096u32;
+    let (parent_backend, child_backend) = make_parent_and_child(virtual_size, block_size);
+
+    // Populate the parent through its own dynamic VHD so it has a mix of allocated sectors.
+    let mut parent = VhdDisk::open(parent_backend).unwrap();
+    let mut expected: Vec<u8> = (0..virtual_size as usize)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    parent.write_at(0, &expected[..12 * 1024]).unwrap();
+    parent
+        .write_at(20 * 1024 + 512, &expected[20 * 1024 + 512..21 * 1024])
+        .unwrap();
+    expected[12 * 1024..20 * 1024 + 512].fill(0);
+    expected[21 * 1024..].fill(0);
+    let parent_backend = parent.into_backend();
+
+    let mut child = VhdDisk::open_differencing_with_options(
+        child_backend,
+        Box::new(VhdDisk::open(parent_backend.clone()).unwrap()),
+        VhdDifferencingOptions {
+            parent_identity: Some(PARENT_IDENTITY),
+            check_timestamp: true,
+        },
+    )
+    .unwrap();
+
+    let writes: [(u64, usize, u8); 4] = [
+        // Crosses a VHD block boundary mid-sector on both sides.
+        (4096 - 700, 1400, 0xA1),
+        // Crosses the sector 7/8 boundary, i.e. the first byte boundary of the sector bitmap.
+        (7 * 512 + 100, 600, 0xB2),
+        // Sector-aligned run over parent-unallocated sectors in a parent-allocated block.
+        (20 * 1024, 1024, 0xC3),
+        // Tail of the disk, where neither image has data.
+        (virtual_size - 3000, 3000, 0xD4),
+    ];
+    for &(off, len, val) in &writes {
+        child.write_at(off, &vec![val; len]).unwrap();
+        expected[off as usize..off as usize + len].fill(val);
+    }
+
+    let mut actual = vec![0u8; virtual_size as usize];
+    child.read_at(0, &mut actual).unwrap();
+    assert_eq!(actual, expected);
+
+    child.flush().unwrap();
+    let child_backend = child.into_backend();
+    let mut reopened = VhdDisk::open_differencing(
+        child_backend,
+        Box::new(VhdDisk::open(parent_backend).unwrap()),
+    )
+    .unwrap();
+    let mut actual = vec![0u8; virtual_size as usize];
+    reopened.read_at(0, &mut actual).unwrap();
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,694 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use aero_storage::{
+    DiskError, MemBackend, StorageBackend as _, VhdDisk, VhdFooterLocation, VhdProblem,
+    VhdRepairAction, VhdRepairPolicy, VhdValidationReport, VirtualDisk, SECTOR_SIZE,
+    VHD_MAX_REPORTED_BAT_PROBLEMS,
+};
+
+const VIRTUAL_SIZE: u64 = 64 * 1024;
+const BLOCK_SIZE: u32 = 16 * 1024;
+const DYN_HEADER_OFFSET: u64 = SECTOR_SIZE as u64;
+const TABLE_OFFSET: u64 = DYN_HEADER_OFFSET + 1024;
+/// 4 BAT entries padded to one sector.
+const BAT_SIZE: u64 = SECTOR_SIZE as u64;
+/// First byte after the metadata of [`make_vhd_dynamic`] images.
+const DATA_START: u64 = TABLE_OFFSET + BAT_SIZE;
+/// 32 sectors per block need a 4-byte bitmap, padded to one sector.
+const BLOCK_TOTAL_SIZE: u64 = SECTOR_SIZE as u64 + BLOCK_SIZE as u64;
+
+fn write_be_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_be_bytes());
+}
+
+fn write_be_u64(buf: &mut [u8], offset: usize, val: u64) {
+    buf[offset..offset + 8].copy_from_slice(&val.to_be_bytes());
+}
+
+fn vhd_footer_checksum(raw: &[u8; SECTOR_SIZE]) -> u32 {
+    let mut sum: u32 = 0;
+    for (i, b) in raw.iter().enumerate() {
+        if (64..68).contains(&i) {
+            continue;
+        }
+        sum = sum.wrapping_add(*b as u32);
+    }
+    !sum
+}
+
+fn vhd_dynamic_header_checksum(raw: &[u8; 1024]) -> u32 {
+    let mut sum: u32 = 0;
+    for (i, b) in raw.iter().enumerate() {
+        if (36..40).contains(&i) {
+            continue;
+        }
+        sum = sum.wrapping_add(*b as u32);
+    }
+    !sum
+}
+
+fn make_vhd_footer(virtual_size: u64, disk_type: u32, data_offset: u64) -> [u8; SECTOR_SIZE] {
+    let mut footer = [0u8; SECTOR_SIZE];
+    footer[0..8].copy_from_slice(b"conectix");
+    write_be_u32(&mut footer, 8, 2); // features
+    write_be_u32(&mut footer, 12, 0x0001_0000); // file_format_version
+    write_be_u64(&mut footer, 16, data_offset);
+    write_be_u64(&mut footer, 40, virtual_size); // original_size
+    write_be_u64(&mut footer, 48, virtual_size); // current_size
+    write_be_u32(&mut footer, 60, disk_type);
+    let checksum = vhd_footer_checksum(&footer);
+    write_be_u32(&mut footer, 64, checksum);
+    footer
+}
+
+/// 64 KiB fixed disk (no footer copy) with "hello vhd!" in sector 0.
+fn make_vhd_fixed() -> MemBackend {
+    let mut backend = MemBackend::with_len(VIRTUAL_SIZE + SECTOR_SIZE as u64).unwrap();
+    backend.write_at(0, b"hello vhd!").unwrap();
+    backend
+        .write_at(VIRTUAL_SIZE, &make_vhd_footer(VIRTUAL_SIZE, 2, u64::MAX))
+        .unwrap();
+    backend
+}
+
+/// 64 KiB dynamic disk with 16 KiB blocks; blocks 0 and 2 are allocated and their first sectors
+/// hold "block 0" / "block 2".
+fn make_vhd_dynamic() -> MemBackend {
+    let file_len = DATA_START + 2 * BLOCK_TOTAL_SIZE + SECTOR_SIZE as u64;
+    let mut backend = MemBackend::with_len(file_len).unwrap();
+
+    let footer = make_vhd_footer(VIRTUAL_SIZE, 3, DYN_HEADER_OFFSET);
+    backend.write_at(0, &footer).unwrap();
+    backend
+        .write_at(file_len - SECTOR_SIZE as u64, &footer)
+        .unwrap();
+
+    let mut dyn_header = [0u8; 1024];
+    dyn_header[0..8].copy_from_slice(b"cxsparse");
+    write_be_u64(&mut dyn_header, 8, u64::MAX);
+    write_be_u64(&mut dyn_header, 16, TABLE_OFFSET);
+    write_be_u32(&mut dyn_header, 24, 0x0001_0000);
+    write_be_u32(&mut dyn_header, 28, 4); // max_table_entries
+    write_be_u32(&mut dyn_header, 32, BLOCK_SIZE);
+    let checksum = vhd_dynamic_header_checksum(&dyn_header);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    backend.write_at(DYN_HEADER_OFFSET, &dyn_header).unwrap();
+
+    let mut bat = [0xFFu8; BAT_SIZE as usize];
+    for (i, block) in [0usize, 2].into_iter().enumerate() {
+        let block_start = DATA_START + i as u64 * BLOCK_TOTAL_SIZE;
+        write_be_u32(
+            &mut bat,
+            block * 4,
+            (block_start / SECTOR_SIZE as u64) as u32,
+        );
+        backend.write_at(block_start, &[0x80]).unwrap(); // sector 0 present
+        backend
+            .write_at(
+                block_start + SECTOR_SIZE as u64,
+                format!("block {block}").as_bytes(),
+            )
+            .unwrap();
+    }
+    backend.write_at(TABLE_OFFSET, &bat).unwrap();
+
+    backend
+}
+
+fn trailing_footer_offset(backend: &mut MemBackend) -> u64 {
+    backend.len().unwrap() - SECTOR_SIZE as u64
+}
+
+fn read_first_bytes(disk: &mut VhdDisk<MemBackend>, offset: u64) -> [u8; 7] {
+    let mut buf = [0u8; 7];
+    disk.read_at(offset, &mut buf).unwrap();
+    buf
+}
+
+fn problems(backend: &mut MemBackend) -> Vec<VhdProblem> {
+    VhdDisk::validate(backend).unwrap().problems
+}
+
+#[test]
+fn intact_images_validate_clean() {
+    let mut fixed = make_vhd_fixed();
+    let report = VhdDisk::validate(&mut fixed).unwrap();
+    assert_eq!(
+        report,
+        VhdValidationReport {
+            file_len: VIRTUAL_SIZE + SECTOR_SIZE as u64,
+            disk_type: Some(2),
+            current_size: Some(VIRTUAL_SIZE),
+            problems: Vec::new(),
+            bat_entries_out_of_range: 0,
+        }
+    );
+    assert!(report.is_valid());
+
+    let mut dynamic = make_vhd_dynamic();
+    let report = VhdDisk::validate(&mut dynamic).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!(report.disk_type, Some(3));
+}
+
+#[test]
+fn damaged_trailing_footer_is_restored_from_the_copy() {
+    let mut backend = make_vhd_dynamic();
+    let footer_offset = trailing_footer_offset(&mut backend);
+    backend.write_at(footer_offset + 40, &[0xAA]).unwrap(); // original_size, not checksummed
+
+    let found = problems(&mut backend);
+    assert!(
+        matches!(
+            found[..],
+            [VhdProblem::FooterChecksumMismatch {
+                location: VhdFooterLocation::Trailing,
+                ..
+            }]
+        ),
+        "{found:?}"
+    );
+    assert!(matches!(
+        VhdDisk::open(backend.clone()),
+        Err(DiskError::CorruptImage("vhd footer checksum mismatch"))
+    ));
+
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    // The intact copy wins over recomputing the damaged footer's checksum.
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RestoredFooter {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+    assert_eq!(report.before.problems, found);
+    assert!(report.after.is_valid(), "{:?}", report.after);
+    assert_eq!(&read_first_bytes(&mut disk, 0), b"block 0");
+}
+
+#[test]
+fn missing_trailing_footer_cookie_is_restored_from_the_copy() {
+    let mut backend = make_vhd_dynamic();
+    let footer_offset = trailing_footer_offset(&mut backend);
+    backend.write_at(footer_offset, &[0u8; 8]).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::FooterCookieMissing {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RestoredFooter {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+    assert_eq!(
+        &read_first_bytes(&mut disk, 2 * BLOCK_SIZE as u64),
+        b"block 2"
+    );
+}
+
+/// [`make_vhd_dynamic`] with "tail 2!" in the last sector of block 2, the last data sector
+/// before the trailing footer.
+fn make_vhd_dynamic_with_tail() -> MemBackend {
+    let mut backend = make_vhd_dynamic();
+    let block_start = DATA_START + BLOCK_TOTAL_SIZE;
+    backend.write_at(block_start, &[0x80, 0, 0, 0x01]).unwrap(); // sectors 0 and 31 present
+    backend
+        .write_at(
+            block_start + BLOCK_TOTAL_SIZE - SECTOR_SIZE as u64,
+            b"tail 2!",
+        )
+        .unwrap();
+    backend
+}
+
+fn assert_restored_after_truncation(backend: MemBackend, file_len: u64) {
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RestoredFooter {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+    assert!(report.after.is_valid(), "{:?}", report.after);
+    assert_eq!(report.after.file_len, file_len);
+    assert_eq!(
+        &read_first_bytes(&mut disk, 2 * BLOCK_SIZE as u64),
+        b"block 2"
+    );
+    assert_eq!(
+        &read_first_bytes(&mut disk, 3 * BLOCK_SIZE as u64 - SECTOR_SIZE as u64),
+        b"tail 2!"
+    );
+}
+
+#[test]
+fn trailing_footer_cut_at_a_sector_boundary_is_appended_after_the_data() {
+    let mut backend = make_vhd_dynamic_with_tail();
+    let file_len = backend.len().unwrap();
+    backend.set_len(file_len - SECTOR_SIZE as u64).unwrap();
+
+    // The last block now ends the file; it is still in range.
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::FooterCookieMissing {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+    assert!(VhdDisk::open(backend.clone()).is_err());
+
+    assert_restored_after_truncation(backend, file_len);
+}
+
+#[test]
+fn trailing_footer_cut_mid_sector_is_rewritten_at_a_sector_boundary() {
+    let mut backend = make_vhd_dynamic_with_tail();
+    let file_len = backend.len().unwrap();
+    let cut_len = file_len - 200;
+    backend.set_len(cut_len).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [
+            VhdProblem::FileLengthInvalid { len: cut_len },
+            VhdProblem::FooterCookieMissing {
+                location: VhdFooterLocation::Trailing
+            },
+        ]
+    );
+    assert!(VhdDisk::open(backend.clone()).is_err());
+
+    assert_restored_after_truncation(backend, file_len);
+}
+
+#[test]
+fn damaged_footer_copy_is_restored_from_the_trailing_footer() {
+    let mut backend = make_vhd_dynamic();
+    backend.write_at(0, &[0u8; SECTOR_SIZE]).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::FooterCookieMissing {
+            location: VhdFooterLocation::Copy
+        }]
+    );
+
+    let (_, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RestoredFooter {
+            location: VhdFooterLocation::Copy
+        }]
+    );
+    assert!(report.after.is_valid());
+}
+
+#[test]
+fn footer_copy_mismatch_is_reported_and_resynced() {
+    let mut backend = make_vhd_dynamic();
+    let mut copy = make_vhd_footer(VIRTUAL_SIZE, 3, DYN_HEADER_OFFSET);
+    write_be_u32(&mut copy, 24, 1234); // timestamp
+    let checksum = vhd_footer_checksum(&copy);
+    write_be_u32(&mut copy, 64, checksum);
+    backend.write_at(0, &copy).unwrap();
+
+    assert_eq!(problems(&mut backend), [VhdProblem::FooterCopyMismatch]);
+
+    let (_, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RestoredFooter {
+            location: VhdFooterLocation::Copy
+        }]
+    );
+}
+
+#[test]
+fn footer_checksum_without_a_copy_is_recomputed() {
+    let mut backend = make_vhd_fixed();
+    let mut footer = make_vhd_footer(VIRTUAL_SIZE, 2, u64::MAX);
+    write_be_u32(&mut footer, 64, 0x1234_5678);
+    backend.write_at(VIRTUAL_SIZE, &footer).unwrap();
+
+    let expected = vhd_footer_checksum(&footer);
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::FooterChecksumMismatch {
+            location: VhdFooterLocation::Trailing,
+            stored: 0x1234_5678,
+            computed: expected,
+        }]
+    );
+
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RecomputedFooterChecksum {
+            location: VhdFooterLocation::Trailing
+        }]
+    );
+    let mut buf = [0u8; 10];
+    disk.read_at(0, &mut buf).unwrap();
+    assert_eq!(&buf, b"hello vhd!");
+}
+
+#[test]
+fn invalid_footer_fields_are_not_repaired() {
+    let mut backend = make_vhd_fixed();
+    let mut footer = make_vhd_footer(VIRTUAL_SIZE, 2, u64::MAX);
+    write_be_u64(&mut footer, 48, 1000); // current_size not a multiple of the sector size
+    write_be_u32(&mut footer, 64, 0); // also break the checksum
+    backend.write_at(VIRTUAL_SIZE, &footer).unwrap();
+
+    let found = problems(&mut backend);
+    assert_eq!(
+        found[1..],
+        [VhdProblem::FooterInvalid {
+            location: VhdFooterLocation::Trailing,
+            reason: "vhd current_size invalid",
+        }]
+    );
+
+    let report = VhdDisk::repair(&mut backend, VhdRepairPolicy::default()).unwrap();
+    assert!(report.actions.is_empty());
+    assert_eq!(report.after.problems, found);
+}
+
+#[test]
+fn unsupported_disk_type_is_reported() {
+    let mut backend = make_vhd_fixed();
+    backend
+        .write_at(VIRTUAL_SIZE, &make_vhd_footer(VIRTUAL_SIZE, 5, u64::MAX))
+        .unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::DiskTypeUnsupported { disk_type: 5 }]
+    );
+}
+
+#[test]
+fn geometry_larger_than_current_size_is_reported_but_not_fatal() {
+    let mut backend = make_vhd_fixed();
+    let mut footer = make_vhd_footer(VIRTUAL_SIZE, 2, u64::MAX);
+    // 2 * 4 * 17 sectors = 68 KiB > 64 KiB.
+    footer[56..60].copy_from_slice(&[0, 2, 4, 17]);
+    let checksum = vhd_footer_checksum(&footer);
+    write_be_u32(&mut footer, 64, checksum);
+    backend.write_at(VIRTUAL_SIZE, &footer).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::GeometryMismatch {
+            cylinders: 2,
+            heads: 4,
+            sectors_per_track: 17,
+            current_size: VIRTUAL_SIZE,
+        }]
+    );
+
+    // Geometry is informational: there is nothing to repair and the image still opens.
+    let (_, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert!(report.actions.is_empty());
+    assert_eq!(report.after.problems.len(), 1);
+}
+
+#[test]
+fn truncated_fixed_disk_is_reported() {
+    let mut backend = MemBackend::with_len(32 * 1024 + SECTOR_SIZE as u64).unwrap();
+    backend
+        .write_at(32 * 1024, &make_vhd_footer(VIRTUAL_SIZE, 2, u64::MAX))
+        .unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::FixedDiskTruncated {
+            len: 32 * 1024 + SECTOR_SIZE as u64,
+            required_len: VIRTUAL_SIZE + SECTOR_SIZE as u64,
+        }]
+    );
+    assert!(VhdDisk::open_repair(backend, VhdRepairPolicy::default()).is_err());
+}
+
+#[test]
+fn dynamic_header_out_of_bounds_is_reported() {
+    let mut backend = make_vhd_dynamic();
+    let footer_offset = trailing_footer_offset(&mut backend);
+    let footer = make_vhd_footer(VIRTUAL_SIZE, 3, footer_offset);
+    backend.write_at(0, &footer).unwrap();
+    backend.write_at(footer_offset, &footer).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::DynamicHeaderOutOfBounds {
+            offset: footer_offset
+        }]
+    );
+}
+
+#[test]
+fn dynamic_header_checksum_is_recomputed() {
+    let mut backend = make_vhd_dynamic();
+    backend.write_at(DYN_HEADER_OFFSET + 36, &[0; 4]).unwrap();
+
+    let found = problems(&mut backend);
+    assert!(
+        matches!(
+            found[..],
+            [VhdProblem::DynamicHeaderChecksumMismatch { stored: 0, .. }]
+        ),
+        "{found:?}"
+    );
+
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::RecomputedDynamicHeaderChecksum]
+    );
+    assert_eq!(&read_first_bytes(&mut disk, 0), b"block 0");
+}
+
+#[test]
+fn missing_dynamic_header_cookie_is_not_repairable() {
+    let mut backend = make_vhd_dynamic();
+    backend.write_at(DYN_HEADER_OFFSET, b"garbage!").unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::DynamicHeaderCookieMissing {
+            offset: DYN_HEADER_OFFSET
+        }]
+    );
+    assert!(matches!(
+        VhdDisk::open_repair(backend, VhdRepairPolicy::default()),
+        Err(DiskError::CorruptImage(
+            "vhd dynamic header cookie mismatch"
+        ))
+    ));
+}
+
+#[test]
+fn bat_too_small_for_current_size_is_reported() {
+    let mut backend = make_vhd_dynamic();
+    let mut dyn_header = [0u8; 1024];
+    backend.read_at(DYN_HEADER_OFFSET, &mut dyn_header).unwrap();
+    write_be_u32(&mut dyn_header, 28, 2);
+    let checksum = vhd_dynamic_header_checksum(&dyn_header);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    backend.write_at(DYN_HEADER_OFFSET, &dyn_header).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::BatTooSmall {
+            max_table_entries: 2,
+            required_entries: 4,
+        }]
+    );
+}
+
+#[test]
+fn bat_past_end_of_file_is_reported() {
+    let mut backend = make_vhd_dynamic();
+    let file_len = backend.len().unwrap();
+    let mut dyn_header = [0u8; 1024];
+    backend.read_at(DYN_HEADER_OFFSET, &mut dyn_header).unwrap();
+    write_be_u64(&mut dyn_header, 16, file_len);
+    let checksum = vhd_dynamic_header_checksum(&dyn_header);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    backend.write_at(DYN_HEADER_OFFSET, &dyn_header).unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::BatOutOfBounds {
+            table_offset: file_len,
+            table_bytes: BAT_SIZE,
+            file_len,
+        }]
+    );
+}
+
+#[test]
+fn out_of_range_bat_entries_are_clamped_to_unallocated() {
+    let mut backend = make_vhd_dynamic();
+    // Block 1 points past the trailing footer, block 3 into the BAT.
+    backend
+        .write_at(TABLE_OFFSET + 4, &0x0010_0000u32.to_be_bytes())
+        .unwrap();
+    backend
+        .write_at(
+            TABLE_OFFSET + 12,
+            &((TABLE_OFFSET / SECTOR_SIZE as u64) as u32).to_be_bytes(),
+        )
+        .unwrap();
+
+    let report = VhdDisk::validate(&mut backend).unwrap();
+    assert_eq!(
+        report.problems,
+        [
+            VhdProblem::BatEntryOutOfRange {
+                index: 1,
+                sector: 0x0010_0000,
+            },
+            VhdProblem::BatEntryOutOfRange {
+                index: 3,
+                sector: (TABLE_OFFSET / SECTOR_SIZE as u64) as u32,
+            },
+        ]
+    );
+    assert_eq!(report.bat_entries_out_of_range, 2);
+    assert!(matches!(
+        VhdDisk::open(backend.clone()),
+        Err(DiskError::CorruptImage(_))
+    ));
+
+    let (mut disk, report) = VhdDisk::open_repair(backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::ClampedBatEntries { count: 2 }]
+    );
+    assert!(report.after.is_valid());
+
+    // The clamped blocks read as zeros; the intact ones keep their data.
+    assert_eq!(read_first_bytes(&mut disk, BLOCK_SIZE as u64), [0; 7]);
+    assert_eq!(read_first_bytes(&mut disk, 3 * BLOCK_SIZE as u64), [0; 7]);
+    assert_eq!(&read_first_bytes(&mut disk, 0), b"block 0");
+    assert_eq!(
+        &read_first_bytes(&mut disk, 2 * BLOCK_SIZE as u64),
+        b"block 2"
+    );
+}
+
+#[test]
+fn overlapping_blocks_are_reported() {
+    let mut backend = make_vhd_dynamic();
+    let block0 = (DATA_START / SECTOR_SIZE as u64) as u32;
+    backend
+        .write_at(TABLE_OFFSET + 4, &block0.to_be_bytes())
+        .unwrap();
+
+    assert_eq!(
+        problems(&mut backend),
+        [VhdProblem::BlocksOverlap {
+            first: 0,
+            second: 1
+        }]
+    );
+}
+
+#[test]
+fn reported_bat_problems_are_capped() {
+    // 256 KiB in 512-byte blocks: more BAT entries than the report lists individually.
+    let entries = 512u32;
+    let block_size = SECTOR_SIZE as u32;
+    let virtual_size = u64::from(entries) * u64::from(block_size);
+    let bat_size = u64::from(entries) * 4;
+    let file_len = TABLE_OFFSET + bat_size + SECTOR_SIZE as u64;
+    let mut backend = MemBackend::with_len(file_len).unwrap();
+    let footer = make_vhd_footer(virtual_size, 3, DYN_HEADER_OFFSET);
+    backend.write_at(0, &footer).unwrap();
+    backend
+        .write_at(file_len - SECTOR_SIZE as u64, &footer)
+        .unwrap();
+    let mut dyn_header = [0u8; 1024];
+    dyn_header[0..8].copy_from_slice(b"cxsparse");
+    write_be_u64(&mut dyn_header, 8, u64::MAX);
+    write_be_u64(&mut dyn_header, 16, TABLE_OFFSET);
+    write_be_u32(&mut dyn_header, 24, 0x0001_0000);
+    write_be_u32(&mut dyn_header, 28, entries);
+    write_be_u32(&mut dyn_header, 32, block_size);
+    let checksum = vhd_dynamic_header_checksum(&dyn_header);
+    write_be_u32(&mut dyn_header, 36, checksum);
+    backend.write_at(DYN_HEADER_OFFSET, &dyn_header).unwrap();
+    // Every entry points at sector 0.
+    backend
+        .write_at(TABLE_OFFSET, &vec![0u8; bat_size as usize])
+        .unwrap();
+
+    let report = VhdDisk::validate(&mut backend).unwrap();
+    assert_eq!(report.problems.len(), VHD_MAX_REPORTED_BAT_PROBLEMS);
+    assert_eq!(report.bat_entries_out_of_range, u64::from(entries));
+
+    let report = VhdDisk::repair(&mut backend, VhdRepairPolicy::default()).unwrap();
+    assert_eq!(
+        report.actions,
+        [VhdRepairAction::ClampedBatEntries {
+            count: u64::from(entries)
+        }]
+    );
+    assert!(report.after.is_valid());
+}
+
+#[test]
+fn disabled_repairs_leave_the_image_untouched() {
+    let mut backend = make_vhd_dynamic();
+    let footer_offset = trailing_footer_offset(&mut backend);
+    backend.write_at(footer_offset + 40, &[0xAA]).unwrap();
+    backend.write_at(DYN_HEADER_OFFSET + 36, &[0; 4]).unwrap();
+    backend
+        .write_at(TABLE_OFFSET + 4, &0x0010_0000u32.to_be_bytes())
+        .unwrap();
+    let before = backend.clone();
+
+    let policy = VhdRepairPolicy {
+        restore_footers: false,
+        recompute_checksums: false,
+        clamp_bat_entries: false,
+    };
+    let report = VhdDisk::repair(&mut backend, policy).unwrap();
+    assert!(report.actions.is_empty());
+    assert_eq!(report.after, report.before);
+    assert_eq!(report.before.problems.len(), 3);
+
+    let len = backend.len().unwrap() as usize;
+    let mut after_bytes = vec![0u8; len];
+    backend.read_at(0, &mut after_bytes).unwrap();
+    let mut before_bytes = vec![0u8; len];
+    before.clone().read_at(0, &mut before_bytes).unwrap();
+    assert!(after_bytes == before_bytes);
+}
+
+#[test]
+fn report_serializes_for_the_host_ui() {
+    let mut backend = make_vhd_dynamic();
+    backend
+        .write_at(TABLE_OFFSET + 4, &0x0010_0000u32.to_be_bytes())
+        .unwrap();
+
+    let report = VhdDisk::repair(&mut backend, VhdRepairPolicy::default()).unwrap();
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(
+        json["before"]["problems"][0],
+        serde_json::json!({"kind": "batEntryOutOfRange", "index": 1, "sector": 0x0010_0000})
+    );
+    assert_eq!(json["before"]["batEntriesOutOfRange"], 1);
+    assert_eq!(
+        json["actions"][0],
+        serde_json::json!({"kind": "clampedBatEntries", "count": 1})
+    );
+    assert_eq!(json["after"]["problems"], serde_json::json!([]));
+}
@@ -706,7 +706,7 @@ fn peek_snapshot_device_id(bytes: &[u8]) -> SnapshotResult<[u8; 4]> {
 fn is_known_usb_device_model_device_id(device_id: &[u8; 4]) -> bool {
     matches!(
         device_id,
-        b"UHUB" | b"UKBD" | b"UMSE" | b"UGPD" | b"UCON" | b"UCMP" | b"HIDP" | b"WUSB"
+        b"UHUB" | b"UKBD" | b"UMSE" | b"UGPD" | b"UCON" | b"UCMP" | b"HIDP" | b"WUSB" | b"UPRX"
     )
 }
 
@@ -738,6 +738,9 @@ fn usb_device_model_snapshot_device_id(model: &dyn UsbDeviceModel) -> Option<[u8
     if any.is::<crate::UsbWebUsbPassthroughDevice>() {
         return Some(crate::UsbWebUsbPassthroughDevice::DEVICE_ID);
     }
+    if any.is::<crate::ProxyUsbDevice>() {
+        return Some(crate::ProxyUsbDevice::DEVICE_ID);
+    }
     None
 }
 
@@ -771,6 +774,10 @@ fn try_new_usb_device_model_from_snapshot(
                 .map(|h| Box::new(h) as Box<dyn UsbDeviceModel>),
         ),
         b"WUSB" => Ok(Some(Box::new(crate::UsbWebUsbPassthroughDevice::new()))),
+        b"UPRX" => Ok(
+            crate::ProxyUsbDevice::try_new_from_snapshot(model_snapshot)?
+                .map(|dev| Box::new(dev) as Box<dyn UsbDeviceModel>),
+        ),
         _ => Ok(None),
     }
 }
@@ -826,6 +833,9 @@ fn save_usb_device_model_snapshot(model: &dyn UsbDeviceModel) -> Option<Vec<u8>>
     if let Some(dev) = any.downcast_ref::<crate::UsbWebUsbPassthroughDevice>() {
         return Some(dev.save_state());
     }
+    if let Some(dev) = any.downcast_ref::<crate::ProxyUsbDevice>() {
+        return Some(dev.save_state());
+    }
 
     None
 }
@@ -897,6 +907,11 @@ fn apply_usb_device_model_snapshot(
                 return dev.load_state(bytes);
             }
         }
+        b"UPRX" => {
+            if let Some(dev) = any.downcast_mut::<crate::ProxyUsbDevice>() {
+                return dev.load_state(bytes);
+            }
+        }
         _ => {}
     }
 
@@ -135,7 +135,7 @@ impl Port {
             // - Low-speed devices clear HSP and report idle K-state via LS=0b01.
             // - While resuming at full/low speed, report a K-state (LS=0b01).
             if let Some(dev) = self.device.as_ref() {
-                let speed = dev.speed();
+                let speed = dev.speed().usb2_link_speed();
 
                 // PORTSC.HSP is only meaningful when EHCI owns the port (PORT_OWNER=0).
                 if !self.port_owner && speed == UsbSpeed::High {
@@ -149,7 +149,7 @@ impl Port {
                         0b01
                     } else {
                         match speed {
-                            UsbSpeed::High => 0,
+                            UsbSpeed::High | UsbSpeed::Super => 0,
                             UsbSpeed::Full => 0b10, // J-state (D+ high)
                             UsbSpeed::Low => 0b01,  // K-state (D- high)
                         }
@@ -338,6 +338,11 @@ impl Port {
         }
     }
 
+    /// Whether [`Port::tick_1ms`] (and the attached device's tick) would do nothing.
+    fn is_idle(&self) -> bool {
+        !(self.reset || self.resuming || (self.enabled && self.device.is_some()))
+    }
+
     fn tick_1ms(&mut self) {
         if self.reset {
             self.reset_countdown_ms = self.reset_countdown_ms.saturating_sub(1);
@@ -730,6 +735,15 @@ impl RootHub {
         }
     }
 
+    /// Whether [`RootHub::tick_1ms`] would do nothing: no port has a reset or resume timer running
+    /// or an enabled device attached.
+    pub fn is_idle(&self) -> bool {
+        self.ports.iter().all(|p| match p {
+            RootHubPortSlot::Local(local) => local.is_idle(),
+            RootHubPortSlot::Usb2Mux { mux, port } => mux.borrow().ehci_port_idle(*port),
+        })
+    }
+
     pub fn tick_1ms(&mut self) {
         for p in &mut self.ports {
             match p {
@@ -144,6 +144,21 @@ impl EhciController {
         self.irq_level
     }
 
+    /// Whether [`EhciController::tick_1ms`] would leave the controller unchanged: it is halted, its
+    /// root hub is idle (see [`RootHub::is_idle`]) and the status bits and IRQ level are settled.
+    pub fn is_idle(&self) -> bool {
+        if self.regs.usbcmd & USBCMD_RS != 0 || !self.hub.is_idle() {
+            return false;
+        }
+        let mut regs = self.regs;
+        if self.hub.any_port_change() {
+            regs.usbsts |= USBSTS_PCD;
+        }
+        regs.update_halted();
+        let irq = (regs.usbsts & USBSTS_IRQ_MASK) & (regs.usbintr & USBINTR_MASK) != 0;
+        regs.usbsts == self.regs.usbsts && irq == self.irq_level
+    }
+
     pub fn hub_mut(&mut self) -> &mut RootHub {
         &mut self.hub
     }
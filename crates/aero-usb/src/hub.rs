@@ -139,8 +139,8 @@ impl Port {
             match dev.speed() {
                 UsbSpeed::Low => v |= LSDA,
                 // UHCI is USB 1.1 (low/full speed). The root hub PORTSC LSDA bit only reports
-                // low-speed; treat high-speed devices as full-speed here.
-                UsbSpeed::Full | UsbSpeed::High => {}
+                // low-speed; treat high-speed (and SuperSpeed) devices as full-speed here.
+                UsbSpeed::Full | UsbSpeed::High | UsbSpeed::Super => {}
             }
         }
         if self.reset {
@@ -249,6 +249,11 @@ impl Port {
         }
     }
 
+    /// Whether [`Port::tick_1ms`] (and the attached device's tick) would do nothing.
+    fn is_idle(&self) -> bool {
+        !(self.reset || self.resuming || (self.enabled && self.device.is_some()))
+    }
+
     fn tick_1ms(&mut self) {
         if self.reset {
             self.reset_countdown_ms = self.reset_countdown_ms.saturating_sub(1);
@@ -657,6 +662,15 @@ impl RootHub {
         self.ports[port].write_portsc_masked(value, write_mask);
     }
 
+    /// Whether [`RootHub::tick_1ms`] would do nothing: no port has a reset or resume timer running
+    /// or an enabled device attached.
+    pub fn is_idle(&self) -> bool {
+        self.ports.iter().all(|p| match p {
+            RootHubPortSlot::Local(local) => local.is_idle(),
+            RootHubPortSlot::Usb2Mux { mux, port } => mux.borrow().uhci_port_idle(*port),
+        })
+    }
+
     pub fn tick_1ms(&mut self) {
         for p in &mut self.ports {
             match p {
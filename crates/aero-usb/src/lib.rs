@@ -30,6 +30,7 @@
 //! | `hid::UsbHidPassthrough` / `hid::UsbHidPassthroughHandle` | `b"HIDP"` |
 //! | `passthrough::UsbPassthroughDevice` | `b"USBP"` |
 //! | `passthrough_device::UsbWebUsbPassthroughDevice` | `b"WUSB"` |
+//! | `proxy_device::ProxyUsbDevice` | `b"UPRX"` |
 //!
 //! `UhciController` snapshots include the full USB topology: each hub port stores an
 //! [`device::AttachedUsbDevice`] (`ADEV`) snapshot, and `ADEV` snapshots embed a nested snapshot of
@@ -48,6 +49,7 @@ pub mod hub;
 pub mod memory;
 pub mod passthrough;
 pub mod passthrough_device;
+pub mod proxy_device;
 pub mod uhci;
 pub mod usb2_port;
 pub mod web;
@@ -60,6 +62,7 @@ extern crate alloc;
 pub use device::{UsbInResult, UsbOutResult};
 pub use memory::MemoryBus;
 pub use passthrough_device::UsbWebUsbPassthroughDevice;
+pub use proxy_device::{ProxyUsbDescriptors, ProxyUsbDevice, ProxyUsbResult};
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
@@ -80,6 +83,21 @@ pub enum UsbSpeed {
     /// report `High` so snapshotting and cross-controller plumbing can round-trip device
     /// identity/speed.
     High,
+    /// USB 3.x SuperSpeed (5Gbps).
+    ///
+    /// Only xHCI USB 3 root ports train a SuperSpeed link. Everywhere else a SuperSpeed device
+    /// falls back to its USB 2.0 high-speed interface (see [`UsbSpeed::usb2_link_speed`]).
+    Super,
+}
+
+impl UsbSpeed {
+    /// Speed the device runs at when connected to a USB 2.0 (or USB 1.1) port.
+    pub fn usb2_link_speed(self) -> UsbSpeed {
+        match self {
+            UsbSpeed::Super => UsbSpeed::High,
+            other => other,
+        }
+    }
 }
 
 /// USB control transfer SETUP packet.
@@ -336,6 +354,25 @@ pub trait UsbDeviceModel: Any {
         self.handle_interrupt_out(ep, data)
     }
 
+    /// Handles one isochronous IN service interval.
+    ///
+    /// `frame` is the controller's 1ms frame counter at the time the TD is serviced, which lets
+    /// host-backed device models pace payload delivery against guest time rather than wall-clock
+    /// time. Isochronous transfers have no handshake: [`UsbInResult::Nak`] is treated as a
+    /// zero-length packet, and `Stall`/`Timeout` complete the TD with a transaction error.
+    ///
+    /// The default implementation always returns a zero-length packet.
+    fn handle_isoch_in_transfer(&mut self, _ep: u8, _frame: u64, _max_len: usize) -> UsbInResult {
+        UsbInResult::Data(Vec::new())
+    }
+
+    /// Handles one isochronous OUT service interval.
+    ///
+    /// The default implementation accepts and discards the payload.
+    fn handle_isoch_out_transfer(&mut self, _ep: u8, _frame: u64, _data: &[u8]) -> UsbOutResult {
+        UsbOutResult::Ack
+    }
+
     /// Polls an interrupt IN endpoint and returns the next queued report (if any).
     ///
     /// Returning `None` indicates the endpoint would NAK.
@@ -64,7 +64,7 @@ pub enum UsbHostAction {
 }
 
 impl UsbHostAction {
-    fn id(&self) -> u32 {
+    pub(crate) fn id(&self) -> u32 {
         match self {
             UsbHostAction::ControlIn { id, .. } => *id,
             UsbHostAction::ControlOut { id, .. } => *id,
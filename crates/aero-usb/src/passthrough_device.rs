@@ -7,6 +7,7 @@
 //! While a host action is in-flight, transfers return NAK so the guest's TD remains active and is
 //! retried in a later frame.
 
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::rc::Rc;
 use alloc::vec::Vec;
 use core::cell::RefCell;
@@ -23,10 +24,35 @@ use crate::passthrough::{
 };
 use crate::{ControlResponse, SetupPacket, UsbDeviceModel, UsbSpeed};
 
+/// Maximum number of isochronous payloads buffered per endpoint/direction.
+///
+/// Isochronous streams are lossy by design; when the host (IN) or guest (OUT) side falls behind,
+/// the oldest payloads are dropped rather than growing the queue without bound.
+const MAX_ISOCH_QUEUED_PAYLOADS: usize = 64;
+
+/// An isochronous OUT payload produced by the guest, tagged with the frame it was serviced in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsbIsochOutPayload {
+    pub endpoint: u8,
+    pub frame: u64,
+    pub data: Vec<u8>,
+}
+
 #[derive(Debug)]
 struct WebUsbPassthroughState {
     passthrough: UsbPassthroughDevice,
     speed: UsbSpeed,
+    /// Host-supplied isochronous IN payloads, consumed one per service interval.
+    isoch_in: BTreeMap<u8, VecDeque<Vec<u8>>>,
+    /// Guest-produced isochronous OUT payloads awaiting host consumption.
+    isoch_out: VecDeque<UsbIsochOutPayload>,
+}
+
+impl WebUsbPassthroughState {
+    fn clear_isoch(&mut self) {
+        self.isoch_in.clear();
+        self.isoch_out.clear();
+    }
 }
 
 /// Shareable handle for a WebUSB passthrough USB device model.
@@ -46,6 +72,8 @@ impl UsbWebUsbPassthroughDevice {
         Self(Rc::new(RefCell::new(WebUsbPassthroughState {
             passthrough: UsbPassthroughDevice::new(),
             speed,
+            isoch_in: BTreeMap::new(),
+            isoch_out: VecDeque::new(),
         })))
     }
 
@@ -73,8 +101,29 @@ impl UsbWebUsbPassthroughDevice {
         self.0.borrow().passthrough.pending_summary()
     }
 
+    /// Queues an isochronous IN payload for `endpoint`.
+    ///
+    /// Each guest service interval consumes at most one payload; intervals with nothing queued
+    /// complete as zero-length packets. Host integrations should feed payloads at the device's
+    /// nominal rate (e.g. one audio frame per millisecond).
+    pub fn push_isoch_in(&self, endpoint: u8, data: Vec<u8>) {
+        let mut inner = self.0.borrow_mut();
+        let queue = inner.isoch_in.entry(endpoint).or_default();
+        if queue.len() >= MAX_ISOCH_QUEUED_PAYLOADS {
+            queue.pop_front();
+        }
+        queue.push_back(data);
+    }
+
+    /// Drains isochronous OUT payloads produced by the guest, in service order.
+    pub fn drain_isoch_out(&self) -> Vec<UsbIsochOutPayload> {
+        self.0.borrow_mut().isoch_out.drain(..).collect()
+    }
+
     pub fn reset(&self) {
-        self.0.borrow_mut().passthrough.reset();
+        let mut inner = self.0.borrow_mut();
+        inner.passthrough.reset();
+        inner.clear_isoch();
     }
 
     /// Clears host-side WebUSB bookkeeping without changing guest-visible USB state.
@@ -84,7 +133,9 @@ impl UsbWebUsbPassthroughDevice {
     /// TD retries will re-emit host actions instead of deadlocking on a completion that will never
     /// arrive.
     pub fn reset_host_state_for_restore(&self) {
-        self.0.borrow_mut().passthrough.reset();
+        let mut inner = self.0.borrow_mut();
+        inner.passthrough.reset();
+        inner.clear_isoch();
     }
 
     fn to_host_setup(setup: SetupPacket) -> HostSetupPacket {
@@ -147,7 +198,7 @@ impl UsbDeviceModel for UsbWebUsbPassthroughDevice {
     }
 
     fn reset(&mut self) {
-        self.0.borrow_mut().passthrough.reset();
+        UsbWebUsbPassthroughDevice::reset(self);
     }
 
     fn cancel_control_transfer(&mut self) {
@@ -184,6 +235,31 @@ impl UsbDeviceModel for UsbWebUsbPassthroughDevice {
             .handle_out_transfer(ep, data);
         Self::map_out_result(resp)
     }
+
+    fn handle_isoch_in_transfer(&mut self, ep: u8, _frame: u64, max_len: usize) -> UsbInResult {
+        let mut data = self
+            .0
+            .borrow_mut()
+            .isoch_in
+            .get_mut(&ep)
+            .and_then(|queue| queue.pop_front())
+            .unwrap_or_default();
+        data.truncate(max_len);
+        UsbInResult::Data(data)
+    }
+
+    fn handle_isoch_out_transfer(&mut self, ep: u8, frame: u64, data: &[u8]) -> UsbOutResult {
+        let mut inner = self.0.borrow_mut();
+        if inner.isoch_out.len() >= MAX_ISOCH_QUEUED_PAYLOADS {
+            inner.isoch_out.pop_front();
+        }
+        inner.isoch_out.push_back(UsbIsochOutPayload {
+            endpoint: ep,
+            frame,
+            data: data.to_vec(),
+        });
+        UsbOutResult::Ack
+    }
 }
 
 impl IoSnapshot for UsbWebUsbPassthroughDevice {
@@ -222,6 +298,7 @@ impl IoSnapshot for UsbWebUsbPassthroughDevice {
 
         let mut inner = self.0.borrow_mut();
         inner.passthrough.reset();
+        inner.clear_isoch();
         inner.speed = UsbSpeed::Full;
 
         if let Some(raw) = r.u8(TAG_SPEED)? {
@@ -242,22 +319,24 @@ impl IoSnapshot for UsbWebUsbPassthroughDevice {
     }
 }
 
-fn encode_speed(speed: UsbSpeed) -> u8 {
+pub(crate) fn encode_speed(speed: UsbSpeed) -> u8 {
     match speed {
         UsbSpeed::Full => 0,
         UsbSpeed::Low => 1,
         // Stable encoding (legacy snapshots used `2` for high-speed before `UsbSpeed::High` was
         // temporarily removed).
         UsbSpeed::High => 2,
+        UsbSpeed::Super => 3,
     }
 }
 
-fn decode_speed(val: u8) -> SnapshotResult<UsbSpeed> {
+pub(crate) fn decode_speed(val: u8) -> SnapshotResult<UsbSpeed> {
     match val {
         0 => Ok(UsbSpeed::Full),
         1 => Ok(UsbSpeed::Low),
         // Back-compat: older snapshots used 2 for high speed when the enum variant was removed.
         2 => Ok(UsbSpeed::High),
+        3 => Ok(UsbSpeed::Super),
         _ => Err(aero_io_snapshot::io::state::SnapshotError::InvalidFieldEncoding("usb speed")),
     }
 }
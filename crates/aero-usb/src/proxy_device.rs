@@ -0,0 +1,400 @@
+//! Generic host-proxied USB device model.
+//!
+//! [`ProxyUsbDevice`] exposes a guest-visible USB device whose descriptors are registered up front
+//! by the host and whose control/bulk/interrupt transfers are forwarded to the host
+//! asynchronously (e.g. to a `navigator.usb` device in the browser).
+//!
+//! - Standard `GET_DESCRIPTOR` requests for the registered device/configuration/string
+//!   descriptors are answered locally, so enumeration does not round-trip through the host.
+//! - Every other transfer is queued as a [`UsbHostAction`] (drained with
+//!   [`ProxyUsbDevice::take_requests`]) and completed later with [`ProxyUsbDevice::complete`].
+//! - While a transfer is outstanding the endpoint NAKs, so the guest's TD stays active and is
+//!   retried in a later frame instead of blocking the controller.
+//!
+//! Snapshots record the registered descriptors (so the device can be reconstructed and the host
+//! knows to re-bind it) but never in-flight transfers: those are backed by host promises that
+//! cannot survive a restore.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
+use aero_io_snapshot::io::state::{
+    IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
+};
+
+use crate::device::{UsbInResult, UsbOutResult};
+use crate::passthrough::{
+    ControlResponse as HostControlResponse, SetupPacket as HostSetupPacket, UsbHostAction,
+    UsbHostCompletion, UsbHostCompletionIn, UsbHostCompletionOut, UsbInResult as HostUsbInResult,
+    UsbOutResult as HostUsbOutResult, UsbPassthroughDevice,
+};
+use crate::passthrough_device::{decode_speed, encode_speed};
+use crate::{ControlResponse, SetupPacket, UsbDeviceModel, UsbSpeed};
+
+const USB_REQUEST_GET_DESCRIPTOR: u8 = 0x06;
+const USB_DESCRIPTOR_TYPE_DEVICE: u8 = 0x01;
+const USB_DESCRIPTOR_TYPE_CONFIGURATION: u8 = 0x02;
+const USB_DESCRIPTOR_TYPE_STRING: u8 = 0x03;
+
+/// Descriptors registered by the host when a [`ProxyUsbDevice`] is created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyUsbDescriptors {
+    /// Guest-visible bus speed.
+    pub speed: UsbSpeed,
+    /// Raw 18-byte device descriptor.
+    pub device: Vec<u8>,
+    /// Full configuration descriptor blob (configuration + interface + endpoint descriptors) for
+    /// configuration index 0.
+    pub configuration: Vec<u8>,
+    /// String descriptors indexed by string index; entry 0 is the LANGID table. Empty entries are
+    /// forwarded to the host instead of being answered locally.
+    pub strings: Vec<Vec<u8>>,
+}
+
+impl Default for ProxyUsbDescriptors {
+    fn default() -> Self {
+        Self {
+            speed: UsbSpeed::Full,
+            device: Vec::new(),
+            configuration: Vec::new(),
+            strings: Vec::new(),
+        }
+    }
+}
+
+/// Host-side result for a transfer previously returned by [`ProxyUsbDevice::take_requests`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProxyUsbResult {
+    /// The transfer succeeded. IN transfers carry the returned bytes; the payload is ignored for
+    /// OUT transfers.
+    Data(Vec<u8>),
+    /// The device stalled the transfer.
+    Stall,
+}
+
+#[derive(Debug)]
+struct ProxyUsbState {
+    descriptors: ProxyUsbDescriptors,
+    passthrough: UsbPassthroughDevice,
+    /// Requests handed to the host that have not been completed yet, keyed by request id.
+    outstanding: BTreeMap<u32, UsbHostAction>,
+    /// Set once the host detaches the device; all further transfers stall.
+    detached: bool,
+}
+
+impl ProxyUsbState {
+    fn clear_host_state(&mut self) {
+        self.passthrough.reset();
+        self.outstanding.clear();
+    }
+}
+
+/// Shareable handle for a host-proxied USB device model.
+///
+/// Clone the handle before attaching it to a hub so the host integration can keep draining
+/// requests and pushing completions.
+#[derive(Clone, Debug)]
+pub struct ProxyUsbDevice(Rc<RefCell<ProxyUsbState>>);
+
+impl ProxyUsbDevice {
+    pub fn new(descriptors: ProxyUsbDescriptors) -> Self {
+        Self(Rc::new(RefCell::new(ProxyUsbState {
+            descriptors,
+            passthrough: UsbPassthroughDevice::new(),
+            outstanding: BTreeMap::new(),
+            detached: false,
+        })))
+    }
+
+    pub fn descriptors(&self) -> ProxyUsbDescriptors {
+        self.0.borrow().descriptors.clone()
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.0.borrow().detached
+    }
+
+    /// Drains guest-initiated transfers that the host has not seen yet.
+    ///
+    /// Each returned request stays outstanding (and its endpoint keeps NAKing) until it is passed
+    /// to [`ProxyUsbDevice::complete`].
+    pub fn take_requests(&self) -> Vec<UsbHostAction> {
+        let mut inner = self.0.borrow_mut();
+        let requests = inner.passthrough.drain_actions();
+        for req in &requests {
+            inner.outstanding.insert(req.id(), req.clone());
+        }
+        requests
+    }
+
+    /// Completes an outstanding request.
+    ///
+    /// Returns `false` if `request_id` is not outstanding (already completed, canceled by a newer
+    /// SETUP, or dropped by a reset/detach); such completions are ignored.
+    pub fn complete(&self, request_id: u32, result: ProxyUsbResult) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let Some(req) = inner.outstanding.remove(&request_id) else {
+            return false;
+        };
+        let completion = match (req, result) {
+            (UsbHostAction::ControlIn { id, .. }, ProxyUsbResult::Data(data)) => {
+                UsbHostCompletion::ControlIn {
+                    id,
+                    result: UsbHostCompletionIn::Success { data },
+                }
+            }
+            (UsbHostAction::ControlIn { id, .. }, ProxyUsbResult::Stall) => {
+                UsbHostCompletion::ControlIn {
+                    id,
+                    result: UsbHostCompletionIn::Stall,
+                }
+            }
+            (UsbHostAction::ControlOut { id, data, .. }, ProxyUsbResult::Data(_)) => {
+                UsbHostCompletion::ControlOut {
+                    id,
+                    result: UsbHostCompletionOut::Success {
+                        bytes_written: data.len() as u32,
+                    },
+                }
+            }
+            (UsbHostAction::ControlOut { id, .. }, ProxyUsbResult::Stall) => {
+                UsbHostCompletion::ControlOut {
+                    id,
+                    result: UsbHostCompletionOut::Stall,
+                }
+            }
+            (UsbHostAction::BulkIn { id, .. }, ProxyUsbResult::Data(data)) => {
+                UsbHostCompletion::BulkIn {
+                    id,
+                    result: UsbHostCompletionIn::Success { data },
+                }
+            }
+            (UsbHostAction::BulkIn { id, .. }, ProxyUsbResult::Stall) => {
+                UsbHostCompletion::BulkIn {
+                    id,
+                    result: UsbHostCompletionIn::Stall,
+                }
+            }
+            (UsbHostAction::BulkOut { id, data, .. }, ProxyUsbResult::Data(_)) => {
+                UsbHostCompletion::BulkOut {
+                    id,
+                    result: UsbHostCompletionOut::Success {
+                        bytes_written: data.len() as u32,
+                    },
+                }
+            }
+            (UsbHostAction::BulkOut { id, .. }, ProxyUsbResult::Stall) => {
+                UsbHostCompletion::BulkOut {
+                    id,
+                    result: UsbHostCompletionOut::Stall,
+                }
+            }
+        };
+        inner.passthrough.push_completion(completion);
+        true
+    }
+
+    /// Number of requests handed to the host that are still awaiting completion.
+    pub fn outstanding_requests(&self) -> usize {
+        self.0.borrow().outstanding.len()
+    }
+
+    /// Marks the device as unplugged by the host.
+    ///
+    /// Queued and outstanding requests are dropped and every subsequent transfer (including
+    /// retries of transfers that were in flight) completes with STALL, so no guest TD is left
+    /// waiting for a completion that will never arrive.
+    pub fn detach(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.clear_host_state();
+        inner.detached = true;
+    }
+
+    fn local_descriptor(&self, setup: SetupPacket) -> Option<Vec<u8>> {
+        if setup.bm_request_type != 0x80 || setup.b_request != USB_REQUEST_GET_DESCRIPTOR {
+            return None;
+        }
+        let inner = self.0.borrow();
+        let desc = &inner.descriptors;
+        let [index, desc_type] = setup.w_value.to_le_bytes();
+        let bytes = match desc_type {
+            USB_DESCRIPTOR_TYPE_DEVICE if index == 0 => &desc.device,
+            USB_DESCRIPTOR_TYPE_CONFIGURATION if index == 0 => &desc.configuration,
+            USB_DESCRIPTOR_TYPE_STRING => desc.strings.get(usize::from(index))?,
+            _ => return None,
+        };
+        if bytes.is_empty() {
+            return None;
+        }
+        let len = bytes.len().min(usize::from(setup.w_length));
+        Some(bytes[..len].to_vec())
+    }
+
+    fn to_host_setup(setup: SetupPacket) -> HostSetupPacket {
+        HostSetupPacket {
+            bm_request_type: setup.bm_request_type,
+            b_request: setup.b_request,
+            w_value: setup.w_value,
+            w_index: setup.w_index,
+            w_length: setup.w_length,
+        }
+    }
+}
+
+impl UsbDeviceModel for ProxyUsbDevice {
+    fn speed(&self) -> UsbSpeed {
+        self.0.borrow().descriptors.speed
+    }
+
+    fn reset(&mut self) {
+        self.0.borrow_mut().clear_host_state();
+    }
+
+    fn reset_host_state_for_restore(&mut self) {
+        self.0.borrow_mut().clear_host_state();
+    }
+
+    fn cancel_control_transfer(&mut self) {
+        let mut inner = self.0.borrow_mut();
+        inner.passthrough.cancel_control_transfer();
+        inner.outstanding.retain(|_, req| {
+            !matches!(
+                req,
+                UsbHostAction::ControlIn { .. } | UsbHostAction::ControlOut { .. }
+            )
+        });
+    }
+
+    fn handle_control_request(
+        &mut self,
+        setup: SetupPacket,
+        data_stage: Option<&[u8]>,
+    ) -> ControlResponse {
+        if self.is_detached() {
+            return ControlResponse::Stall;
+        }
+        if let Some(data) = self.local_descriptor(setup) {
+            return ControlResponse::Data(data);
+        }
+        let resp = self
+            .0
+            .borrow_mut()
+            .passthrough
+            .handle_control_request(Self::to_host_setup(setup), data_stage);
+        match resp {
+            HostControlResponse::Data(data) => ControlResponse::Data(data),
+            HostControlResponse::Ack => ControlResponse::Ack,
+            HostControlResponse::Nak => ControlResponse::Nak,
+            HostControlResponse::Stall => ControlResponse::Stall,
+            HostControlResponse::Timeout => ControlResponse::Timeout,
+        }
+    }
+
+    fn handle_in_transfer(&mut self, ep: u8, max_len: usize) -> UsbInResult {
+        if self.is_detached() {
+            return UsbInResult::Stall;
+        }
+        let resp = self
+            .0
+            .borrow_mut()
+            .passthrough
+            .handle_in_transfer(ep, max_len);
+        match resp {
+            HostUsbInResult::Data(data) => UsbInResult::Data(data),
+            HostUsbInResult::Nak => UsbInResult::Nak,
+            HostUsbInResult::Stall => UsbInResult::Stall,
+            HostUsbInResult::Timeout => UsbInResult::Timeout,
+        }
+    }
+
+    fn handle_out_transfer(&mut self, ep: u8, data: &[u8]) -> UsbOutResult {
+        if self.is_detached() {
+            return UsbOutResult::Stall;
+        }
+        let resp = self
+            .0
+            .borrow_mut()
+            .passthrough
+            .handle_out_transfer(ep, data);
+        match resp {
+            HostUsbOutResult::Ack => UsbOutResult::Ack,
+            HostUsbOutResult::Nak => UsbOutResult::Nak,
+            HostUsbOutResult::Stall => UsbOutResult::Stall,
+            HostUsbOutResult::Timeout => UsbOutResult::Timeout,
+        }
+    }
+}
+
+const UPRX_TAG_SPEED: u16 = 1;
+const UPRX_TAG_DEVICE_DESCRIPTOR: u16 = 2;
+const UPRX_TAG_CONFIG_DESCRIPTOR: u16 = 3;
+const UPRX_TAG_STRING_DESCRIPTORS: u16 = 4;
+
+impl ProxyUsbDevice {
+    fn decode_descriptors(r: &SnapshotReader<'_>) -> SnapshotResult<ProxyUsbDescriptors> {
+        let mut desc = ProxyUsbDescriptors::default();
+        if let Some(raw) = r.u8(UPRX_TAG_SPEED)? {
+            desc.speed = decode_speed(raw)?;
+        }
+        if let Some(buf) = r.bytes(UPRX_TAG_DEVICE_DESCRIPTOR) {
+            desc.device = buf.to_vec();
+        }
+        if let Some(buf) = r.bytes(UPRX_TAG_CONFIG_DESCRIPTOR) {
+            desc.configuration = buf.to_vec();
+        }
+        if let Some(buf) = r.bytes(UPRX_TAG_STRING_DESCRIPTORS) {
+            let mut d = Decoder::new(buf);
+            desc.strings = d.vec_bytes()?;
+            d.finish()?;
+        }
+        Ok(desc)
+    }
+
+    pub(crate) fn try_new_from_snapshot(bytes: &[u8]) -> SnapshotResult<Option<Self>> {
+        let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
+        r.ensure_device_major(Self::DEVICE_VERSION.major)?;
+        if r.bytes(UPRX_TAG_DEVICE_DESCRIPTOR).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(Self::decode_descriptors(&r)?)))
+    }
+}
+
+impl IoSnapshot for ProxyUsbDevice {
+    const DEVICE_ID: [u8; 4] = *b"UPRX";
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
+        let inner = self.0.borrow();
+        let desc = &inner.descriptors;
+        w.field_u8(UPRX_TAG_SPEED, encode_speed(desc.speed));
+        w.field_bytes(UPRX_TAG_DEVICE_DESCRIPTOR, desc.device.clone());
+        w.field_bytes(UPRX_TAG_CONFIG_DESCRIPTOR, desc.configuration.clone());
+        w.field_bytes(
+            UPRX_TAG_STRING_DESCRIPTORS,
+            Encoder::new().vec_bytes(&desc.strings).finish(),
+        );
+        w.finish()
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
+        let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
+        r.ensure_device_major(Self::DEVICE_VERSION.major)?;
+        let descriptors = Self::decode_descriptors(&r)?;
+        if descriptors.device.is_empty() {
+            return Err(SnapshotError::InvalidFieldEncoding(
+                "proxy usb device descriptor",
+            ));
+        }
+
+        let mut inner = self.0.borrow_mut();
+        inner.descriptors = descriptors;
+        inner.clear_host_state();
+        inner.detached = false;
+        Ok(())
+    }
+}
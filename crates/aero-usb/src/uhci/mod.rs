@@ -41,6 +41,17 @@ impl UhciController {
         self.irq_level
     }
 
+    /// Whether [`UhciController::tick_1ms`] would leave the controller unchanged: it is halted, its
+    /// root hub is idle (see [`RootHub::is_idle`]) and the status bits and IRQ level are settled.
+    pub fn is_idle(&self) -> bool {
+        if self.regs.usbcmd & (USBCMD_RS | USBCMD_EGSM) == USBCMD_RS || !self.hub.is_idle() {
+            return false;
+        }
+        self.regs.usbsts & USBSTS_HCHALTED != 0
+            && self.port_resume_detect() == self.prev_port_resume_detect
+            && self.irq_pending() == self.irq_level
+    }
+
     pub fn hub_mut(&mut self) -> &mut RootHub {
         &mut self.hub
     }
@@ -85,6 +96,10 @@ impl UhciController {
     }
 
     fn update_irq(&mut self) {
+        self.irq_level = self.irq_pending();
+    }
+
+    fn irq_pending(&self) -> bool {
         let mut pending = false;
         if self.regs.usbsts & USBSTS_USBINT != 0
             && ((self.regs.usbint_causes & USBINT_CAUSE_IOC != 0
@@ -101,7 +116,13 @@ impl UhciController {
         if self.regs.usbsts & USBSTS_RESUMEDETECT != 0 && self.regs.usbintr & USBINTR_RESUME != 0 {
             pending = true;
         }
-        self.irq_level = pending;
+        pending
+    }
+
+    /// Whether any root hub port asserts its Resume Detect (RD) bit.
+    fn port_resume_detect(&self) -> bool {
+        const PORTSC_RD: u16 = 1 << 6;
+        (self.hub.read_portsc(0) | self.hub.read_portsc(1)) & PORTSC_RD != 0
     }
 
     fn write_usbcmd(&mut self, value: u16) {
@@ -336,8 +357,7 @@ impl UhciController {
         //
         // This provides a realistic interrupt path for remote-wake style flows where software
         // enables USBINTR.RESUME and expects USBSTS.RESUMEDETECT to latch from port events.
-        const PORTSC_RD: u16 = 1 << 6;
-        let rd = (self.hub.read_portsc(0) | self.hub.read_portsc(1)) & PORTSC_RD != 0;
+        let rd = self.port_resume_detect();
         if rd && !self.prev_port_resume_detect {
             self.regs.usbsts |= USBSTS_RESUMEDETECT;
         }
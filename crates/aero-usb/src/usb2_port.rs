@@ -92,6 +92,11 @@ impl Usb2PortMux {
         p.on_physical_detach();
     }
 
+    /// Returns the controller currently routed to `port`, or `None` if the port does not exist.
+    pub fn port_owner(&self, port: usize) -> Option<Usb2PortOwner> {
+        Some(self.ports.get(port)?.effective_owner)
+    }
+
     pub fn port_device(&self, port: usize) -> Option<&AttachedUsbDevice> {
         self.ports.get(port)?.device.as_ref()
     }
@@ -139,6 +144,13 @@ impl Usb2PortMux {
             .tick_1ms(&mut p.device, RemoteWakeBehavior::ResumeDetect);
     }
 
+    /// Whether [`Usb2PortMux::uhci_tick_1ms`] would do nothing for `port`.
+    pub fn uhci_port_idle(&self, port: usize) -> bool {
+        self.ports.get(port).is_none_or(|p| {
+            p.effective_owner != Usb2PortOwner::Companion || p.uhci.is_idle(&p.device)
+        })
+    }
+
     pub fn uhci_bus_reset(&mut self, port: usize) {
         let Some(p) = self.ports.get_mut(port) else {
             return;
@@ -261,6 +273,13 @@ impl Usb2PortMux {
             .tick_1ms(&mut p.device, RemoteWakeBehavior::EnterResume);
     }
 
+    /// Whether [`Usb2PortMux::ehci_tick_1ms`] would do nothing for `port`.
+    pub fn ehci_port_idle(&self, port: usize) -> bool {
+        self.ports
+            .get(port)
+            .is_none_or(|p| p.effective_owner != Usb2PortOwner::Ehci || p.ehci.is_idle(&p.device))
+    }
+
     pub fn ehci_bus_reset(&mut self, port: usize) {
         let Some(p) = self.ports.get_mut(port) else {
             return;
@@ -457,7 +476,7 @@ impl Usb2MuxPort {
             // Speed reporting:
             // - High-speed devices set HSP (only when EHCI owns the port).
             // - Full/low-speed devices are distinguished via LS (line status).
-            let speed = dev.speed();
+            let speed = dev.speed().usb2_link_speed();
             if self.effective_owner == Usb2PortOwner::Ehci && speed == UsbSpeed::High {
                 v |= HSP;
             }
@@ -471,7 +490,7 @@ impl Usb2MuxPort {
                     0b01
                 } else {
                     match speed {
-                        UsbSpeed::High => 0b00,
+                        UsbSpeed::High | UsbSpeed::Super => 0b00,
                         UsbSpeed::Full => 0b10,
                         UsbSpeed::Low => 0b01,
                     }
@@ -868,6 +887,11 @@ impl PortLogic {
         }
     }
 
+    /// Whether [`PortLogic::tick_1ms`] (and the attached device's tick) would do nothing.
+    fn is_idle(&self, dev: &Option<AttachedUsbDevice>) -> bool {
+        !(self.reset || self.resuming || (self.enabled && dev.is_some()))
+    }
+
     fn tick_1ms(&mut self, dev: &mut Option<AttachedUsbDevice>, remote_wake: RemoteWakeBehavior) {
         if self.reset {
             self.reset_countdown_ms = self.reset_countdown_ms.saturating_sub(1);
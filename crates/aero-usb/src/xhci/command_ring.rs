@@ -1042,6 +1042,7 @@ impl CommandRingProcessor {
             UsbSpeed::Full => 1,
             UsbSpeed::Low => 2,
             UsbSpeed::High => 3,
+            UsbSpeed::Super => 4,
         };
         let mut out_slot_ctx = SlotContext::read_from(mem, out_slot_addr);
         out_slot_ctx.set_speed(psiv);
@@ -81,7 +81,7 @@ use core::fmt;
 
 use crate::device::{AttachedUsbDevice, UsbInResult, UsbOutResult};
 use crate::hub::UsbHubDevice;
-use crate::{MemoryBus, SetupPacket, UsbDeviceModel, UsbHubAttachError};
+use crate::{MemoryBus, SetupPacket, UsbDeviceModel, UsbHubAttachError, UsbSpeed};
 
 use self::port::XhciPort;
 use self::trb::{CompletionCode, Trb, TrbType};
@@ -220,6 +220,27 @@ impl UsbDeviceModel for UsbDeviceModelPtr {
         unsafe { self.model_mut().handle_out_transfer(ep, data) }
     }
 
+    fn handle_isoch_in_transfer(
+        &mut self,
+        ep: u8,
+        frame: u64,
+        max_len: usize,
+    ) -> crate::UsbInResult {
+        unsafe {
+            self.model_mut()
+                .handle_isoch_in_transfer(ep, frame, max_len)
+        }
+    }
+
+    fn handle_isoch_out_transfer(
+        &mut self,
+        ep: u8,
+        frame: u64,
+        data: &[u8],
+    ) -> crate::UsbOutResult {
+        unsafe { self.model_mut().handle_isoch_out_transfer(ep, frame, data) }
+    }
+
     fn as_hub(&self) -> Option<&dyn crate::hub::UsbHub> {
         unsafe { self.model().as_hub() }
     }
@@ -432,6 +453,8 @@ impl EndpointOutcome {
 /// to host unit tests that need a stable controller surface.
 pub struct XhciController {
     port_count: u8,
+    /// Number of USB3 root ports. These follow the USB2 ports (`port_count - usb3_port_count..`).
+    usb3_port_count: u8,
     ext_caps: Vec<u32>,
 
     // Minimal MMIO-visible register file for emulator PCI/MMIO integration.
@@ -509,6 +532,7 @@ impl fmt::Debug for XhciController {
             .count();
         f.debug_struct("XhciController")
             .field("port_count", &self.port_count)
+            .field("usb3_port_count", &self.usb3_port_count)
             .field("ext_caps_dwords", &self.ext_caps.len())
             .field("usbcmd", &self.usbcmd)
             .field("usbsts", &self.usbsts)
@@ -550,7 +574,20 @@ impl XhciController {
         Self::default()
     }
 
+    /// Create a controller whose root ports are all USB2.
     pub fn with_port_count(port_count: u8) -> Self {
+        Self::with_port_layout(port_count, 0)
+    }
+
+    /// Create a controller with `usb2_ports` USB2 root ports followed by `usb3_ports` USB3 root
+    /// ports.
+    ///
+    /// Each range is advertised through its own Supported Protocol capability, so guests map
+    /// 1-based port IDs `1..=usb2_ports` to USB 2.0 and the remainder to USB 3.0.
+    pub fn with_port_layout(usb2_ports: u8, usb3_ports: u8) -> Self {
+        let port_count = usb2_ports
+            .checked_add(usb3_ports)
+            .expect("xHCI controller exposes at most 255 ports");
         assert!(
             port_count > 0,
             "xHCI controller must expose at least one port"
@@ -564,6 +601,7 @@ impl XhciController {
             core::iter::repeat_with(|| None).take(slot_count).collect();
         let mut ctrl = Self {
             port_count,
+            usb3_port_count: usb3_ports,
             ext_caps: Vec::new(),
             usbcmd: 0,
             usbsts: 0,
@@ -574,7 +612,15 @@ impl XhciController {
             config: 0,
             slots,
             cmd_kick: false,
-            ports: (0..port_count).map(|_| XhciPort::new()).collect(),
+            ports: (0..port_count)
+                .map(|i| {
+                    if i < usb2_ports {
+                        XhciPort::new()
+                    } else {
+                        XhciPort::new_usb3()
+                    }
+                })
+                .collect(),
             command_ring: None,
             interrupter0: InterrupterRegs::default(),
             event_ring: EventRingProducer::default(),
@@ -598,6 +644,48 @@ impl XhciController {
         self.port_count
     }
 
+    /// Number of USB2 root ports (0-based indices `0..usb2_port_count()`).
+    pub fn usb2_port_count(&self) -> u8 {
+        self.port_count - self.usb3_port_count
+    }
+
+    /// Number of USB3 root ports (they follow the USB2 ports).
+    pub fn usb3_port_count(&self) -> u8 {
+        self.usb3_port_count
+    }
+
+    /// Whether root port `port` (0-based) is a USB3 port.
+    pub fn port_is_usb3(&self, port: usize) -> bool {
+        self.ports.get(port).is_some_and(|p| p.is_usb3())
+    }
+
+    /// Whether a device of `speed` can be connected directly to root port `port` (0-based).
+    ///
+    /// USB3 root ports only detect SuperSpeed devices; USB2 ports accept any speed (SuperSpeed
+    /// devices fall back to high-speed).
+    pub fn port_accepts_speed(&self, port: usize, speed: UsbSpeed) -> bool {
+        match self.ports.get(port) {
+            Some(p) if p.is_usb3() => speed == UsbSpeed::Super,
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Protocol Speed ID for a device at `root_port` (1-based) + `route`, as the link runs.
+    fn link_speed_id(&self, root_port: u8, route: &[u8], speed: UsbSpeed) -> u8 {
+        // Hubs are modelled as USB2 hubs, so only a device directly on a USB3 root port runs at
+        // SuperSpeed.
+        let usb3_link = route.is_empty()
+            && root_port
+                .checked_sub(1)
+                .is_some_and(|idx| self.port_is_usb3(usize::from(idx)));
+        if usb3_link {
+            port::port_speed_id(speed)
+        } else {
+            port::port_speed_id(speed.usb2_link_speed())
+        }
+    }
+
     /// Attach a USB device model at a host-visible topology path.
     ///
     /// Path numbering matches the `hub::RootHub` contract used by UHCI:
@@ -630,6 +718,9 @@ impl XhciController {
             if self.ports[root_port].has_device() {
                 return Err(UsbHubAttachError::PortOccupied);
             }
+            if !self.port_accepts_speed(root_port, model.speed()) {
+                return Err(UsbHubAttachError::InvalidPort);
+            }
             self.attach_device(root_port, model);
             return Ok(());
         }
@@ -727,7 +818,7 @@ impl XhciController {
         // Clamp to the maximum downstream port number representable in a Route String nibble.
         let port_count = port_count.min(context::XHCI_ROUTE_STRING_MAX_PORT);
         let root_port = root_port as usize;
-        if root_port >= self.ports.len() {
+        if root_port >= self.ports.len() || self.port_is_usb3(root_port) {
             return Err(UsbHubAttachError::InvalidPort);
         }
         self.attach_device(
@@ -1360,7 +1451,7 @@ impl XhciController {
                     }
                 }
 
-                port::port_speed_id(dev.speed())
+                dev.speed()
             }
             None => {
                 self.queue_command_completion_event(
@@ -1371,6 +1462,7 @@ impl XhciController {
                 return;
             }
         };
+        let expected_speed = self.link_speed_id(port_id, &route, expected_speed);
         slot_ctx.set_speed(expected_speed);
         // xHCI assigns a device address (USB Device Address field) independent of whether it issues
         // the SET_ADDRESS request (BSR=1 blocks the request but does not block the assignment).
@@ -1976,9 +2068,10 @@ impl XhciController {
         // Resolve the bound device so we can derive xHC-owned Slot Context fields (speed, address,
         // slot state).
         let expected_speed = match self.find_device_by_topology(root_port, &route) {
-            Some(dev) => port::port_speed_id(dev.speed()),
+            Some(dev) => dev.speed(),
             None => return CommandCompletion::failure(CommandCompletionCode::ContextStateError),
         };
+        let expected_speed = self.link_speed_id(root_port, &route, expected_speed);
 
         let mut slot_ctx = slot_ctx;
         // Mirror controller-owned Slot Context fields to better match Address Device semantics.
@@ -2730,7 +2823,13 @@ impl XhciController {
     }
 
     /// Attach a device model to a root hub port (0-based).
+    ///
+    /// Devices that cannot connect to the port (non-SuperSpeed devices on a USB3 port, see
+    /// [`XhciController::port_accepts_speed`]) are ignored.
     pub fn attach_device(&mut self, port: usize, dev: Box<dyn UsbDeviceModel>) {
+        if !self.port_accepts_speed(port, dev.speed()) {
+            return;
+        }
         // Replace any existing device (host-side convenience).
         if self.ports.get(port).is_some_and(|p| p.has_device()) {
             self.detach_device(port);
@@ -2813,45 +2912,82 @@ impl XhciController {
         caps.push(usb_legsup);
         caps.push(0);
 
+        let usb2_ports = self.usb2_port_count();
+        let usb3_ports = self.usb3_port_count;
+
         // Supported Protocol Capability for USB 2.0.
         //
-        // The roothub port range is 1-based, so we expose all ports as a single USB 2.0 range.
-        let psic = 3u8; // low/full/high-speed entries.
-        let header0 = (regs::EXT_CAP_ID_SUPPORTED_PROTOCOL as u32)
-            // Next pointer: 0 => end of list.
-            | ((regs::USB_REVISION_2_0 as u32) << 16);
-        caps.push(header0);
-        caps.push(regs::PROTOCOL_NAME_USB2);
-        caps.push((1u32) | ((self.port_count as u32) << 8));
-        // DWORD3: PSIC (0..=15) + Protocol Slot Type + PSI descriptor table offset.
-        //
-        // The PSI descriptor table begins immediately after DWORD3, at offset 4 dwords from the
-        // start of the capability.
-        let psio = 4u16;
-        caps.push(
-            (psic as u32) | ((regs::USB2_PROTOCOL_SLOT_TYPE as u32) << 8) | ((psio as u32) << 16),
-        );
+        // The roothub port range is 1-based; USB2 ports come first.
+        const USB2_PROTOCOL_CAP_DWORDS: u32 = 7;
+        if usb2_ports > 0 {
+            let psic = 3u8; // low/full/high-speed entries.
+                            // Next pointer: the USB 3.0 capability (if any) directly follows this one.
+            let next = if usb3_ports > 0 {
+                supported_protocol_offset_dwords + USB2_PROTOCOL_CAP_DWORDS
+            } else {
+                0
+            };
+            let header0 = (regs::EXT_CAP_ID_SUPPORTED_PROTOCOL as u32)
+                | (next << 8)
+                | ((regs::USB_REVISION_2_0 as u32) << 16);
+            caps.push(header0);
+            caps.push(regs::PROTOCOL_NAME_USB2);
+            caps.push((1u32) | ((usb2_ports as u32) << 8));
+            // DWORD3: PSIC (0..=15) + Protocol Slot Type + PSI descriptor table offset.
+            //
+            // The PSI descriptor table begins immediately after DWORD3, at offset 4 dwords from
+            // the start of the capability.
+            let psio = 4u16;
+            caps.push(
+                (psic as u32)
+                    | ((regs::USB2_PROTOCOL_SLOT_TYPE as u32) << 8)
+                    | ((psio as u32) << 16),
+            );
 
-        // Protocol Speed ID descriptors.
-        // These values are consumed by guest xHCI drivers to interpret PORTSC.PS values.
-        caps.push(regs::encode_psi(
-            regs::PSIV_FULL_SPEED,
-            regs::PSI_TYPE_FULL,
-            12,
-            1,
-        ));
-        caps.push(regs::encode_psi(
-            regs::PSIV_LOW_SPEED,
-            regs::PSI_TYPE_LOW,
-            15,
-            0,
-        ));
-        caps.push(regs::encode_psi(
-            regs::PSIV_HIGH_SPEED,
-            regs::PSI_TYPE_HIGH,
-            48,
-            2,
-        ));
+            // Protocol Speed ID descriptors.
+            // These values are consumed by guest xHCI drivers to interpret PORTSC.PS values.
+            caps.push(regs::encode_psi(
+                regs::PSIV_FULL_SPEED,
+                regs::PSI_TYPE_FULL,
+                12,
+                1,
+            ));
+            caps.push(regs::encode_psi(
+                regs::PSIV_LOW_SPEED,
+                regs::PSI_TYPE_LOW,
+                15,
+                0,
+            ));
+            caps.push(regs::encode_psi(
+                regs::PSIV_HIGH_SPEED,
+                regs::PSI_TYPE_HIGH,
+                48,
+                2,
+            ));
+        }
+
+        // Supported Protocol Capability for USB 3.0, covering the ports after the USB2 range.
+        if usb3_ports > 0 {
+            let psic = 1u8; // SuperSpeed entry.
+            let header0 = (regs::EXT_CAP_ID_SUPPORTED_PROTOCOL as u32)
+                // Next pointer: 0 => end of list.
+                | ((regs::USB_REVISION_3_0 as u32) << 16);
+            caps.push(header0);
+            caps.push(regs::PROTOCOL_NAME_USB3);
+            caps.push(u32::from(usb2_ports + 1) | (u32::from(usb3_ports) << 8));
+            let psio = 4u16;
+            caps.push(
+                (psic as u32)
+                    | ((regs::USB3_PROTOCOL_SLOT_TYPE as u32) << 8)
+                    | ((psio as u32) << 16),
+            );
+            caps.push(regs::encode_psi(
+                regs::PSIV_SUPER_SPEED,
+                regs::PSI_TYPE_SUPER,
+                5,
+                3,
+            ));
+        }
 
         caps
     }
@@ -3300,8 +3436,8 @@ impl XhciController {
             return EndpointOutcome::idle();
         }
 
-        // Bulk/interrupt endpoints are delegated to `transfer::XhciTransferExecutor` and execute at
-        // most one TD per controller tick.
+        // Bulk/interrupt/isoch endpoints are delegated to `transfer::XhciTransferExecutor` and
+        // execute at most one TD per controller tick.
         if endpoint_id != 1 {
             let Some(ep_addr) = Self::ep_addr_from_endpoint_id(endpoint_id) else {
                 return EndpointOutcome::idle();
@@ -3345,6 +3481,10 @@ impl XhciController {
                 return EndpointOutcome::idle();
             }
 
+            // Isoch TDs are scheduled against the MFINDEX frame number (MFINDEX counts 125µs
+            // microframes; Isoch TRB Frame IDs are in 1ms frames).
+            exec.set_frame_clock(self.time_ms, (self.mfindex >> 3) as u16);
+
             let before = exec
                 .endpoint_state(ep_addr)
                 .map(|st| (st.ring.dequeue_ptr, st.ring.cycle));
@@ -3416,11 +3556,15 @@ impl XhciController {
             }
 
             // Keep the endpoint active if the next TRB is ready (or we're waiting on an inflight
-            // device completion).
+            // device completion). Streaming isoch endpoints are serviced every frame so an empty
+            // ring is reported as Ring Underrun/Overrun at the next service interval.
             let keep_active = exec.endpoint_state(ep_addr).is_some_and(|st| {
                 if st.halted {
                     return false;
                 }
+                if st.isoch_streaming() {
+                    return true;
+                }
                 if *ring_poll_budget == 0 {
                     // Global ring-walk budget exhausted; conservatively keep the endpoint active so
                     // we can retry on a future tick.
@@ -3946,6 +4090,11 @@ impl XhciController {
         } else {
             exec.add_endpoint_with_cycle(ep_addr, dequeue_ptr, cycle);
         }
+        let isoch = matches!(
+            self.read_endpoint_type_from_context(mem, slot_id, endpoint_id),
+            Some(context::EndpointType::IsochIn | context::EndpointType::IsochOut)
+        );
+        exec.set_endpoint_isoch(ep_addr, isoch);
         true
     }
 
@@ -3986,7 +4135,9 @@ impl XhciController {
             context::EndpointType::BulkIn
             | context::EndpointType::BulkOut
             | context::EndpointType::InterruptIn
-            | context::EndpointType::InterruptOut => {}
+            | context::EndpointType::InterruptOut
+            | context::EndpointType::IsochIn
+            | context::EndpointType::IsochOut => {}
             context::EndpointType::Control | context::EndpointType::Invalid if endpoint_id == 1 => {
             }
             _ => return None,
@@ -4005,6 +4156,25 @@ impl XhciController {
         Some((dequeue_ptr, (raw & 0x01) != 0))
     }
 
+    fn read_endpoint_type_from_context(
+        &self,
+        mem: &mut dyn MemoryBus,
+        slot_id: u8,
+        endpoint_id: u8,
+    ) -> Option<context::EndpointType> {
+        if slot_id == 0 || endpoint_id == 0 || endpoint_id > 31 || self.dcbaap == 0 {
+            return None;
+        }
+        let dev_ctx_raw = self.read_device_context_ptr_raw(mem, slot_id)?;
+        let dev_ctx_ptr = dev_ctx_raw & !0x3f;
+        if dev_ctx_ptr == 0 || (dev_ctx_raw & 0x3f) != 0 {
+            return None;
+        }
+        let dev_ctx = context::DeviceContext32::new(dev_ctx_ptr);
+        let ep_ctx = dev_ctx.endpoint_context(mem, endpoint_id).ok()?;
+        Some(ep_ctx.endpoint_type())
+    }
+
     fn read_endpoint_state_from_context(
         &self,
         mem: &mut dyn MemoryBus,
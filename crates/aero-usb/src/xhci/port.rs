@@ -5,8 +5,9 @@ use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
 use aero_io_snapshot::io::state::{IoSnapshot, SnapshotError, SnapshotResult};
 
 use super::regs::{
-    PORTSC_CCS, PORTSC_CSC, PORTSC_LWS, PORTSC_PEC, PORTSC_PED, PORTSC_PLC, PORTSC_PLS_MASK,
-    PORTSC_PLS_SHIFT, PORTSC_PP, PORTSC_PR, PORTSC_PRC, PORTSC_PS_MASK, PORTSC_PS_SHIFT,
+    PLS_POLLING, PLS_RX_DETECT, PLS_U0, PLS_U3, PORTSC_CCS, PORTSC_CSC, PORTSC_LWS, PORTSC_PEC,
+    PORTSC_PED, PORTSC_PLC, PORTSC_PLS_MASK, PORTSC_PLS_SHIFT, PORTSC_PP, PORTSC_PR, PORTSC_PRC,
+    PORTSC_PS_MASK, PORTSC_PS_SHIFT,
 };
 use crate::device::AttachedUsbDevice;
 use crate::{UsbDeviceModel, UsbSpeed};
@@ -14,42 +15,56 @@ use crate::{UsbDeviceModel, UsbSpeed};
 // Reset signalling for USB2 ports is ~50ms (similar to the UHCI root hub model).
 const RESET_DURATION_MS: u16 = 50;
 
+// Time spent in Polling before a SuperSpeed link reaches U0. Real links train in well under 1ms;
+// a few frames keeps the intermediate state observable to drivers polling PORTSC.
+const USB3_LINK_TRAINING_MS: u16 = 2;
+
 /// Maximum bytes allowed for a nested `AttachedUsbDevice` snapshot when restoring.
 ///
 /// This mirrors the limits used by hubs (`crate::hub`).
 const MAX_USB_DEVICE_SNAPSHOT_BYTES: usize = 4 * 1024 * 1024;
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub(crate) enum XhciUsb2LinkState {
+pub(crate) enum XhciPortLinkState {
     /// U0 (active).
     U0,
     /// U3 (suspend).
     U3,
+    /// USB 3 port waiting for a SuperSpeed receiver (nothing connected).
+    RxDetect,
+    /// USB 3 link training in progress.
+    Polling,
 }
 
-impl XhciUsb2LinkState {
+impl XhciPortLinkState {
     fn pls_bits(self) -> u32 {
-        match self {
-            // xHCI spec: PLS encoding.
-            XhciUsb2LinkState::U0 => 0,
-            XhciUsb2LinkState::U3 => 3,
-        }
+        // xHCI spec: PLS encoding.
+        let pls = match self {
+            XhciPortLinkState::U0 => PLS_U0,
+            XhciPortLinkState::U3 => PLS_U3,
+            XhciPortLinkState::RxDetect => PLS_RX_DETECT,
+            XhciPortLinkState::Polling => PLS_POLLING,
+        };
+        u32::from(pls)
     }
 }
 
-fn link_state_from_pls_bits(bits: u8) -> SnapshotResult<XhciUsb2LinkState> {
+fn link_state_from_pls_bits(bits: u8) -> SnapshotResult<XhciPortLinkState> {
     match bits {
-        0 => Ok(XhciUsb2LinkState::U0),
-        3 => Ok(XhciUsb2LinkState::U3),
+        PLS_U0 => Ok(XhciPortLinkState::U0),
+        PLS_U3 => Ok(XhciPortLinkState::U3),
+        PLS_RX_DETECT => Ok(XhciPortLinkState::RxDetect),
+        PLS_POLLING => Ok(XhciPortLinkState::Polling),
         _ => Err(SnapshotError::InvalidFieldEncoding("xhci port link state")),
     }
 }
 
 fn usb_speed_from_port_speed_id(psiv: u8) -> SnapshotResult<UsbSpeed> {
-    // Keep in sync with `port_speed_id` / Supported Protocol PSI IDs (USB2 only).
+    // Keep in sync with `port_speed_id` / Supported Protocol PSI IDs.
     match psiv {
         1 => Ok(UsbSpeed::Full),
         2 => Ok(UsbSpeed::Low),
         3 => Ok(UsbSpeed::High),
+        4 => Ok(UsbSpeed::Super),
         _ => Err(SnapshotError::InvalidFieldEncoding("xhci port speed")),
     }
 }
@@ -59,15 +74,24 @@ pub(crate) fn port_speed_id(speed: UsbSpeed) -> u8 {
     // - 1: full-speed
     // - 2: low-speed
     // - 3: high-speed
+    // and for USB3:
+    // - 4: SuperSpeed
     match speed {
         UsbSpeed::Full => 1,
         UsbSpeed::Low => 2,
         UsbSpeed::High => 3,
+        UsbSpeed::Super => 4,
     }
 }
 
 /// Internal model of an xHCI root hub port.
+///
+/// USB2 ports follow the reset/enable handshake: a connected port stays disabled until the host
+/// issues a port reset. USB3 ports train the link on their own (RxDetect -> Polling -> U0) and
+/// report the connection, already enabled, once the link is up.
 pub(crate) struct XhciPort {
+    /// Whether this is a USB3 (SuperSpeed) port.
+    usb3: bool,
     device: Option<AttachedUsbDevice>,
 
     connected: bool,
@@ -80,14 +104,18 @@ pub(crate) struct XhciPort {
     reset_timer_ms: u16,
     port_reset_change: bool,
 
-    link_state: XhciUsb2LinkState,
+    link_state: XhciPortLinkState,
     port_link_state_change: bool,
     speed: Option<UsbSpeed>,
+
+    /// Remaining USB3 link training time; non-zero while the port is in Polling.
+    link_training_ms: u16,
 }
 
 impl XhciPort {
     pub(crate) fn new() -> Self {
         Self {
+            usb3: false,
             device: None,
             connected: false,
             connect_status_change: false,
@@ -96,12 +124,39 @@ impl XhciPort {
             reset: false,
             reset_timer_ms: 0,
             port_reset_change: false,
-            link_state: XhciUsb2LinkState::U3,
+            link_state: XhciPortLinkState::U3,
             port_link_state_change: false,
             speed: None,
+            link_training_ms: 0,
+        }
+    }
+
+    pub(crate) fn new_usb3() -> Self {
+        Self {
+            usb3: true,
+            link_state: XhciPortLinkState::RxDetect,
+            ..Self::new()
+        }
+    }
+
+    pub(crate) fn is_usb3(&self) -> bool {
+        self.usb3
+    }
+
+    fn disconnected_link_state(&self) -> XhciPortLinkState {
+        if self.usb3 {
+            XhciPortLinkState::RxDetect
+        } else {
+            XhciPortLinkState::U3
         }
     }
 
+    fn start_link_training(&mut self) {
+        self.connected = false;
+        self.link_state = XhciPortLinkState::Polling;
+        self.link_training_ms = USB3_LINK_TRAINING_MS;
+    }
+
     pub(crate) fn host_controller_reset(&mut self) {
         if let Some(dev) = self.device.as_mut() {
             dev.reset();
@@ -119,11 +174,23 @@ impl XhciPort {
         self.port_reset_change = false;
         self.port_link_state_change = false;
 
-        self.link_state = if self.connected {
-            XhciUsb2LinkState::U0
+        self.link_training_ms = 0;
+        if self.usb3 {
+            // A SuperSpeed link retrains after a controller reset; the connection is reported
+            // again once it reaches U0.
+            if self.device.is_some() {
+                self.start_link_training();
+            } else {
+                self.connected = false;
+                self.link_state = XhciPortLinkState::RxDetect;
+            }
         } else {
-            XhciUsb2LinkState::U3
-        };
+            self.link_state = if self.connected {
+                XhciPortLinkState::U0
+            } else {
+                XhciPortLinkState::U3
+            };
+        }
         self.sync_device_suspended_state();
     }
 
@@ -161,6 +228,7 @@ impl XhciPort {
         }
 
         rec = rec.bool(self.port_link_state_change);
+        rec = rec.u16(self.link_training_ms);
         rec.finish()
     }
 
@@ -205,8 +273,15 @@ impl XhciPort {
             Err(SnapshotError::UnexpectedEof) => false,
             Err(e) => return Err(e),
         };
+        // v1.2+: USB3 link training countdown.
+        let link_training_ms = match d.u16() {
+            Ok(v) => v,
+            Err(SnapshotError::UnexpectedEof) => 0,
+            Err(e) => return Err(e),
+        };
         d.finish()?;
         self.port_link_state_change = port_link_state_change;
+        self.link_training_ms = link_training_ms;
 
         if let Some(device_state) = device_state {
             if self.device.is_none() {
@@ -228,13 +303,33 @@ impl XhciPort {
     }
 
     pub(crate) fn attach(&mut self, model: Box<dyn UsbDeviceModel>) -> bool {
-        self.device = Some(AttachedUsbDevice::new(model));
-        self.speed = self.device.as_ref().map(|d| d.speed());
-        self.link_state = XhciUsb2LinkState::U0;
-        self.sync_device_suspended_state();
+        let device = AttachedUsbDevice::new(model);
+        self.speed = Some(if self.usb3 {
+            device.speed()
+        } else {
+            device.speed().usb2_link_speed()
+        });
+        self.device = Some(device);
 
         let mut changed = false;
 
+        if self.usb3 {
+            if self.enabled {
+                self.enabled = false;
+                changed |= self.set_port_enabled_change();
+            }
+            self.reset = false;
+            self.reset_timer_ms = 0;
+            self.port_reset_change = false;
+            // CCS/CSC are reported when training completes (see `tick_1ms`).
+            self.start_link_training();
+            self.sync_device_suspended_state();
+            return changed;
+        }
+
+        self.link_state = XhciPortLinkState::U0;
+        self.sync_device_suspended_state();
+
         // Connecting a new device effectively disables the port until the host performs the
         // reset/enable sequence.
         if self.enabled {
@@ -260,7 +355,8 @@ impl XhciPort {
 
         self.device = None;
         self.speed = None;
-        self.link_state = XhciUsb2LinkState::U3;
+        self.link_state = self.disconnected_link_state();
+        self.link_training_ms = 0;
         self.port_link_state_change = false;
 
         if self.connected {
@@ -351,7 +447,7 @@ impl XhciPort {
                 dev.reset();
             }
             // Reset exits suspend.
-            self.link_state = XhciUsb2LinkState::U0;
+            self.link_state = XhciPortLinkState::U0;
             self.sync_device_suspended_state();
 
             // Per spec, the port is disabled during reset. If that changes PED, surface PEC.
@@ -370,8 +466,8 @@ impl XhciPort {
         if (value & PORTSC_LWS) != 0 {
             let pls = ((value & PORTSC_PLS_MASK) >> PORTSC_PLS_SHIFT) as u8;
             let target = match pls {
-                0 => Some(XhciUsb2LinkState::U0),
-                3 => Some(XhciUsb2LinkState::U3),
+                0 => Some(XhciPortLinkState::U0),
+                3 => Some(XhciPortLinkState::U3),
                 _ => None,
             };
 
@@ -390,6 +486,18 @@ impl XhciPort {
     pub(crate) fn tick_1ms(&mut self) -> bool {
         let mut changed = false;
 
+        if self.link_training_ms != 0 {
+            self.link_training_ms -= 1;
+            if self.link_training_ms == 0 && self.device.is_some() {
+                // Link is up: USB3 ports go straight to Enabled without a host-driven reset.
+                self.connected = true;
+                self.enabled = true;
+                self.link_state = XhciPortLinkState::U0;
+                self.sync_device_suspended_state();
+                changed |= self.set_connect_status_change();
+            }
+        }
+
         if self.reset {
             self.reset_timer_ms = self.reset_timer_ms.saturating_sub(1);
             if self.reset_timer_ms == 0 {
@@ -399,7 +507,7 @@ impl XhciPort {
                 // If a device is still present, the port becomes enabled after reset completes.
                 if self.connected && !self.enabled {
                     self.enabled = true;
-                    self.link_state = XhciUsb2LinkState::U0;
+                    self.link_state = XhciPortLinkState::U0;
                     self.sync_device_suspended_state();
                     changed |= self.set_port_enabled_change();
                 }
@@ -407,17 +515,17 @@ impl XhciPort {
         }
 
         // Remote wakeup for suspended (U3) ports.
-        if self.enabled && self.link_state == XhciUsb2LinkState::U3 {
+        if self.enabled && self.link_state == XhciPortLinkState::U3 {
             let wake = match self.device.as_mut() {
                 Some(dev) => dev.model_mut().poll_remote_wakeup(),
                 None => false,
             };
             if wake {
-                changed |= self.set_link_state(XhciUsb2LinkState::U0);
+                changed |= self.set_link_state(XhciPortLinkState::U0);
             }
         }
 
-        if self.enabled && self.link_state == XhciUsb2LinkState::U0 {
+        if self.enabled && self.link_state == XhciPortLinkState::U0 {
             if let Some(dev) = self.device.as_mut() {
                 dev.tick_1ms();
             }
@@ -452,7 +560,7 @@ impl XhciPort {
         true
     }
 
-    fn set_link_state(&mut self, state: XhciUsb2LinkState) -> bool {
+    fn set_link_state(&mut self, state: XhciPortLinkState) -> bool {
         if self.link_state == state {
             // Even if the link state doesn't change, keep the downstream device's suspended state
             // in sync so snapshot restores and host-side changes cannot leave it stale.
@@ -473,7 +581,7 @@ impl XhciPort {
     fn sync_device_suspended_state(&mut self) {
         if let Some(dev) = self.device.as_mut() {
             dev.model_mut()
-                .set_suspended(self.link_state == XhciUsb2LinkState::U3);
+                .set_suspended(self.link_state == XhciPortLinkState::U3);
         }
     }
 
@@ -273,14 +273,20 @@ pub const USBLEGSUP_OS_OWNED: u32 = 1 << 24;
 
 /// Supported Protocol: Protocol name string "USB ".
 pub const PROTOCOL_NAME_USB2: u32 = u32::from_le_bytes(*b"USB ");
+/// USB 3.x ports use the same "USB " name string; the revision field tells them apart.
+pub const PROTOCOL_NAME_USB3: u32 = PROTOCOL_NAME_USB2;
 
 /// USB revision number encoded as BCD (e.g. USB 2.0 == 0x0200).
 pub const USB_REVISION_2_0: u16 = 0x0200;
+/// USB revision number for the USB 3.0 Supported Protocol capability.
+pub const USB_REVISION_3_0: u16 = 0x0300;
 
 /// Protocol Slot Type used for USB 2.0 ports.
 ///
 /// This value is consumed by some guests when mapping ports to roothub protocol types.
 pub const USB2_PROTOCOL_SLOT_TYPE: u8 = 0x01;
+/// Protocol Slot Type used for USB 3.x ports (same slot type as USB 2.0).
+pub const USB3_PROTOCOL_SLOT_TYPE: u8 = USB2_PROTOCOL_SLOT_TYPE;
 
 // ---- Supported Protocol: Protocol Speed ID Descriptor ----
 
@@ -293,6 +299,8 @@ pub const USB2_PROTOCOL_SLOT_TYPE: u8 = 0x01;
 pub const PSIV_FULL_SPEED: u8 = 1;
 pub const PSIV_LOW_SPEED: u8 = 2;
 pub const PSIV_HIGH_SPEED: u8 = 3;
+/// SuperSpeed (USB 3 ports only).
+pub const PSIV_SUPER_SPEED: u8 = 4;
 
 /// Protocol Speed ID Types (PSIT).
 ///
@@ -300,6 +308,7 @@ pub const PSIV_HIGH_SPEED: u8 = 3;
 pub const PSI_TYPE_LOW: u8 = 1;
 pub const PSI_TYPE_FULL: u8 = 2;
 pub const PSI_TYPE_HIGH: u8 = 3;
+pub const PSI_TYPE_SUPER: u8 = 4;
 
 /// Encodes a Protocol Speed ID Descriptor (PSID).
 ///
@@ -354,6 +363,12 @@ pub const PORTSC_PR: u32 = 1 << 4;
 pub const PORTSC_PLS_SHIFT: u32 = 5;
 pub const PORTSC_PLS_MASK: u32 = 0x0f << PORTSC_PLS_SHIFT;
 
+/// PORTSC.PLS values (xHCI 1.2 §5.4.8, table 5-27).
+pub const PLS_U0: u8 = 0;
+pub const PLS_U3: u8 = 3;
+pub const PLS_RX_DETECT: u8 = 5;
+pub const PLS_POLLING: u8 = 7;
+
 /// Port Power (PP), bit 9.
 pub const PORTSC_PP: u32 = 1 << 9;
 
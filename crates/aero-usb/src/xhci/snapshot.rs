@@ -53,6 +53,8 @@ const TAG_TIME_MS: u16 = 27;
 const TAG_LAST_TICK_DMA_DWORD: u16 = 28;
 // New in snapshot v0.9.
 const TAG_PENDING_DMA_ON_RUN: u16 = 29;
+// New in snapshot v0.10.
+const TAG_USB3_PORT_COUNT: u16 = 30;
 
 const SLOT_CONTEXT_DWORDS: usize = 8;
 const ENDPOINT_CONTEXT_DWORDS: usize = 8;
@@ -513,7 +515,7 @@ fn decode_pending_events(buf: &[u8]) -> SnapshotResult<VecDeque<Trb>> {
 
 impl IoSnapshot for XhciController {
     const DEVICE_ID: [u8; 4] = *b"XHCI";
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(0, 9);
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(0, 10);
 
     fn save_state(&self) -> Vec<u8> {
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
@@ -525,6 +527,7 @@ impl IoSnapshot for XhciController {
         w.field_u32(TAG_USBSTS, self.usbsts_read());
         w.field_u64(TAG_CRCR, self.crcr & regs::CRCR_SNAPSHOT_MASK);
         w.field_u8(TAG_PORT_COUNT, self.port_count);
+        w.field_u8(TAG_USB3_PORT_COUNT, self.usb3_port_count);
         w.field_u64(TAG_DCBAAP, self.dcbaap & regs::DCBAAP_SNAPSHOT_MASK);
         w.field_u32(TAG_CONFIG, self.config & regs::CONFIG_SNAPSHOT_MASK);
         w.field_u32(TAG_MFINDEX, self.mfindex & regs::runtime::MFINDEX_MASK);
@@ -642,15 +645,18 @@ impl IoSnapshot for XhciController {
         }
 
         let port_count = r.u8(TAG_PORT_COUNT)?.unwrap_or(DEFAULT_PORT_COUNT).max(1);
+        // Snapshots before v0.10 only had USB2 ports.
+        let usb3_port_count = r.u8(TAG_USB3_PORT_COUNT)?.unwrap_or(0).min(port_count);
         // Preserve existing port/device instances when possible so snapshot restore can apply
         // device state to host-provided passthrough handles instead of reconstructing devices.
-        let preserved_ports = if port_count == self.port_count {
-            Some(core::mem::take(&mut self.ports))
-        } else {
-            None
-        };
+        let preserved_ports =
+            if port_count == self.port_count && usb3_port_count == self.usb3_port_count {
+                Some(core::mem::take(&mut self.ports))
+            } else {
+                None
+            };
 
-        *self = Self::with_port_count(port_count);
+        *self = Self::with_port_layout(port_count - usb3_port_count, usb3_port_count);
         if let Some(ports) = preserved_ports {
             if ports.len() == self.ports.len() {
                 self.ports = ports;
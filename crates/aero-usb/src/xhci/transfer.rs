@@ -20,6 +20,13 @@ const MAX_LINK_SKIP: usize = 32;
 
 /// Maximum number of TRBs we'll consider part of a single TD (chain).
 const MAX_TD_TRBS: usize = 64;
+
+/// Isoch TRB Frame ID values are 11-bit frame numbers (`MFINDEX >> 3`).
+const ISOCH_FRAME_ID_MASK: u16 = 0x7ff;
+/// Frame IDs within this many frames ahead of the current frame are treated as scheduled in the
+/// future; anything further away is treated as already passed (missed service interval).
+const ISOCH_MAX_SCHEDULE_AHEAD_FRAMES: u16 = 1024;
+
 pub fn read_trb<M: MemoryBus + ?Sized>(mem: &mut M, paddr: u64) -> Trb {
     Trb::read_from(mem, paddr)
 }
@@ -84,9 +91,24 @@ struct BufferSegment {
     len: u32,
 }
 
+/// Scheduling information taken from the Isoch TRB that starts an isochronous TD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IsochTdTiming {
+    sia: bool,
+    frame_id: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IsochSchedule {
+    Due,
+    Future,
+    Missed,
+}
+
 #[derive(Debug, Clone)]
 struct TdDescriptor {
     buffers: Vec<BufferSegment>,
+    isoch: Option<IsochTdTiming>,
     total_len: u32,
     trbs_in_td: usize,
     last_trb_ptr: u64,
@@ -135,6 +157,11 @@ pub struct EndpointState {
     pub ep_addr: u8,
     pub ring: TransferRingState,
     pub halted: bool,
+    /// Whether the endpoint is isochronous (TDs start with Isoch TRBs and are frame-scheduled).
+    pub isoch: bool,
+    /// Set once an isochronous TD has been serviced; cleared when the ring runs dry and a Ring
+    /// Underrun/Overrun event has been reported.
+    isoch_streaming: bool,
 }
 
 impl EndpointState {
@@ -143,6 +170,8 @@ impl EndpointState {
             ep_addr,
             ring: TransferRingState::new(dequeue_ptr),
             halted: false,
+            isoch: false,
+            isoch_streaming: false,
         }
     }
 
@@ -151,9 +180,17 @@ impl EndpointState {
             ep_addr,
             ring: TransferRingState::new_with_cycle(dequeue_ptr, cycle),
             halted: false,
+            isoch: false,
+            isoch_streaming: false,
         }
     }
 
+    /// Returns whether an isochronous endpoint is mid-stream and must keep being serviced every
+    /// frame (even with an empty ring) so Ring Underrun/Overrun can be reported.
+    pub fn isoch_streaming(&self) -> bool {
+        self.isoch && self.isoch_streaming
+    }
+
     fn direction_in(&self) -> bool {
         (self.ep_addr & 0x80) != 0
     }
@@ -167,6 +204,10 @@ pub struct XhciTransferExecutor {
     device: Box<dyn UsbDeviceModel>,
     endpoints: BTreeMap<u8, EndpointState>,
     pending_events: Vec<TransferEvent>,
+    /// Current 1ms frame counter, passed to isochronous device hooks.
+    frame: u64,
+    /// Current 11-bit frame number matched against Isoch TRB Frame IDs.
+    frame_id: u16,
 }
 
 impl fmt::Debug for XhciTransferExecutor {
@@ -174,6 +215,8 @@ impl fmt::Debug for XhciTransferExecutor {
         f.debug_struct("XhciTransferExecutor")
             .field("endpoints", &self.endpoints)
             .field("pending_events", &self.pending_events)
+            .field("frame", &self.frame)
+            .field("frame_id", &self.frame_id)
             .finish_non_exhaustive()
     }
 }
@@ -184,6 +227,8 @@ impl XhciTransferExecutor {
             device,
             endpoints: BTreeMap::new(),
             pending_events: Vec::new(),
+            frame: 0,
+            frame_id: 0,
         }
     }
 
@@ -203,6 +248,28 @@ impl XhciTransferExecutor {
         );
     }
 
+    /// Marks an endpoint as isochronous (or not).
+    ///
+    /// Isochronous endpoints expect TDs that start with an Isoch TRB and are scheduled against the
+    /// frame clock configured via [`XhciTransferExecutor::set_frame_clock`].
+    pub fn set_endpoint_isoch(&mut self, ep_addr: u8, isoch: bool) {
+        if let Some(ep) = self.endpoints.get_mut(&ep_addr) {
+            if ep.isoch != isoch {
+                ep.isoch = isoch;
+                ep.isoch_streaming = false;
+            }
+        }
+    }
+
+    /// Sets the frame clock used for isochronous scheduling.
+    ///
+    /// `frame` is a monotonic 1ms frame counter forwarded to the device model's isochronous hooks;
+    /// `frame_id` is the 11-bit frame number (`MFINDEX >> 3`) compared against Isoch TRB Frame IDs.
+    pub fn set_frame_clock(&mut self, frame: u64, frame_id: u16) {
+        self.frame = frame;
+        self.frame_id = frame_id & ISOCH_FRAME_ID_MASK;
+    }
+
     pub fn endpoint_state(&self, ep_addr: u8) -> Option<&EndpointState> {
         self.endpoints.get(&ep_addr)
     }
@@ -283,6 +350,11 @@ impl XhciTransferExecutor {
             let _ = self.process_one_td(mem, ep, &mut budget);
         }
         self.endpoints = endpoints;
+
+        // Standalone executors own their frame clock; controller integrations override it via
+        // `set_frame_clock` before polling.
+        self.frame = self.frame.wrapping_add(1);
+        self.frame_id = self.frame_id.wrapping_add(1) & ISOCH_FRAME_ID_MASK;
     }
 
     fn process_one_td<M: MemoryBus + ?Sized>(
@@ -314,13 +386,83 @@ impl XhciTransferExecutor {
             }
         };
         if trb.cycle() != ep.ring.cycle {
+            if ep.isoch_streaming() {
+                // The service interval arrived with no TD queued. Report it once; the endpoint
+                // then idles until the guest rings its doorbell again.
+                ep.isoch_streaming = false;
+                self.pending_events.push(TransferEvent {
+                    ep_addr: ep.ep_addr,
+                    trb_ptr: 0,
+                    event_data: None,
+                    residual: 0,
+                    completion_code: if ep.direction_in() {
+                        CompletionCode::RingOverrun
+                    } else {
+                        CompletionCode::RingUnderrun
+                    },
+                });
+            }
             return 0;
         }
 
         match trb.trb_type() {
-            TrbType::Normal => {
+            TrbType::Isoch if ep.isoch => {
                 let mut td = TdDescriptor {
                     buffers: Vec::new(),
+                    isoch: None,
+                    total_len: 0,
+                    trbs_in_td: 0,
+                    last_trb_ptr: ep.ring.dequeue_ptr,
+                    last_ioc: false,
+                    event_data: None,
+                    next_dequeue_ptr: ep.ring.dequeue_ptr,
+                    next_cycle: ep.ring.cycle,
+                };
+
+                match self.gather_td(mem, ep, &mut td, step_budget) {
+                    GatherTdResult::Incomplete => 0,
+                    GatherTdResult::Ready => match self.isoch_schedule(&td) {
+                        IsochSchedule::Future => 0,
+                        IsochSchedule::Due => {
+                            let trbs_in_td = td.trbs_in_td;
+                            ep.isoch_streaming = true;
+                            self.execute_isoch_td(mem, ep, td);
+                            trbs_in_td
+                        }
+                        IsochSchedule::Missed => {
+                            let trbs_in_td = td.trbs_in_td;
+                            ep.isoch_streaming = true;
+                            ep.ring.dequeue_ptr = td.next_dequeue_ptr;
+                            ep.ring.cycle = td.next_cycle;
+                            self.pending_events.push(TransferEvent {
+                                ep_addr: ep.ep_addr,
+                                trb_ptr: td.last_trb_ptr,
+                                event_data: td.event_data,
+                                residual: td.total_len,
+                                completion_code: CompletionCode::MissedServiceError,
+                            });
+                            trbs_in_td
+                        }
+                    },
+                    GatherTdResult::Fault { trb_ptr } => {
+                        ep.halted = true;
+                        self.pending_events.push(TransferEvent {
+                            ep_addr: ep.ep_addr,
+                            trb_ptr,
+                            event_data: None,
+                            residual: 0,
+                            completion_code: CompletionCode::TrbError,
+                        });
+                        0
+                    }
+                    GatherTdResult::BudgetExhausted => 0,
+                }
+            }
+            // Isochronous TDs must start with an Isoch TRB; a leading Normal TRB is a TRB error.
+            TrbType::Normal if !ep.isoch => {
+                let mut td = TdDescriptor {
+                    buffers: Vec::new(),
+                    isoch: None,
                     total_len: 0,
                     trbs_in_td: 0,
                     last_trb_ptr: ep.ring.dequeue_ptr,
@@ -503,6 +645,7 @@ impl XhciTransferExecutor {
         let mut cycle = ep.ring.cycle;
 
         td.buffers.clear();
+        td.isoch = None;
         td.total_len = 0;
         td.trbs_in_td = 0;
         td.last_trb_ptr = ptr;
@@ -543,8 +686,21 @@ impl XhciTransferExecutor {
                     }
                     continue;
                 }
-                TrbType::Normal => {
+                TrbType::Normal | TrbType::Isoch => {
                     let trb_ptr = ptr;
+                    // An Isoch TRB may only start a TD on an isochronous endpoint; subsequent
+                    // fragments of the TD are chained Normal TRBs.
+                    let first = td.trbs_in_td == 0;
+                    let is_isoch_trb = matches!(trb.trb_type(), TrbType::Isoch);
+                    if is_isoch_trb != (first && ep.isoch) {
+                        return GatherTdResult::Fault { trb_ptr };
+                    }
+                    if is_isoch_trb {
+                        td.isoch = Some(IsochTdTiming {
+                            sia: trb.isoch_sia(),
+                            frame_id: trb.isoch_frame_id(),
+                        });
+                    }
                     td.trbs_in_td = td.trbs_in_td.saturating_add(1);
                     let len = trb.transfer_len();
                     td.buffers.push(BufferSegment {
@@ -678,6 +834,86 @@ impl XhciTransferExecutor {
         true
     }
 
+    fn isoch_schedule(&self, td: &TdDescriptor) -> IsochSchedule {
+        let Some(timing) = td.isoch else {
+            return IsochSchedule::Due;
+        };
+        if timing.sia {
+            return IsochSchedule::Due;
+        }
+        let ahead = timing.frame_id.wrapping_sub(self.frame_id) & ISOCH_FRAME_ID_MASK;
+        if ahead == 0 {
+            IsochSchedule::Due
+        } else if ahead <= ISOCH_MAX_SCHEDULE_AHEAD_FRAMES {
+            IsochSchedule::Future
+        } else {
+            IsochSchedule::Missed
+        }
+    }
+
+    /// Executes one isochronous TD in the current frame.
+    ///
+    /// Isochronous transfers have no handshake and never halt the endpoint: NAKs are treated as a
+    /// zero-length packet, and device-side failures complete the TD with a transaction error.
+    fn execute_isoch_td<M: MemoryBus + ?Sized>(
+        &mut self,
+        mem: &mut M,
+        ep: &mut EndpointState,
+        td: TdDescriptor,
+    ) {
+        let max_len = td.total_len as usize;
+        let frame = self.frame;
+
+        let (completion_code, transferred) = if ep.direction_in() {
+            match self
+                .device
+                .handle_isoch_in_transfer(ep.ep_addr, frame, max_len)
+            {
+                UsbInResult::Data(mut data) => {
+                    if data.len() > max_len {
+                        data.truncate(max_len);
+                    }
+                    let transferred = self.dma_write_in(mem, &td.buffers, &data) as u32;
+                    let code = if transferred < td.total_len {
+                        CompletionCode::ShortPacket
+                    } else {
+                        CompletionCode::Success
+                    };
+                    (code, transferred)
+                }
+                UsbInResult::Nak if td.total_len == 0 => (CompletionCode::Success, 0),
+                UsbInResult::Nak => (CompletionCode::ShortPacket, 0),
+                UsbInResult::Stall | UsbInResult::Timeout => {
+                    (CompletionCode::UsbTransactionError, 0)
+                }
+            }
+        } else {
+            let data = self.dma_read_out(mem, &td.buffers, max_len);
+            match self
+                .device
+                .handle_isoch_out_transfer(ep.ep_addr, frame, &data)
+            {
+                UsbOutResult::Ack | UsbOutResult::Nak => (CompletionCode::Success, td.total_len),
+                UsbOutResult::Stall | UsbOutResult::Timeout => {
+                    (CompletionCode::UsbTransactionError, 0)
+                }
+            }
+        };
+
+        ep.ring.dequeue_ptr = td.next_dequeue_ptr;
+        ep.ring.cycle = td.next_cycle;
+
+        if td.last_ioc || completion_code != CompletionCode::Success {
+            self.pending_events.push(TransferEvent {
+                ep_addr: ep.ep_addr,
+                trb_ptr: td.last_trb_ptr,
+                event_data: td.event_data,
+                residual: td.total_len.saturating_sub(transferred),
+                completion_code,
+            });
+        }
+    }
+
     fn dma_write_in<M: MemoryBus + ?Sized>(
         &self,
         mem: &mut M,
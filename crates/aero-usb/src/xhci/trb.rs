@@ -31,8 +31,14 @@ pub enum CompletionCode {
     SlotNotEnabledError = 11,
     EndpointNotEnabledError = 12,
     ShortPacket = 13,
+    /// Isochronous OUT endpoint had no TD queued when its service interval arrived.
+    RingUnderrun = 14,
+    /// Isochronous IN endpoint had no TD queued when its service interval arrived.
+    RingOverrun = 15,
     ParameterError = 17,
     ContextStateError = 19,
+    /// An isochronous TD could not be serviced within its scheduled frame.
+    MissedServiceError = 23,
 }
 
 impl CompletionCode {
@@ -87,6 +93,14 @@ impl Trb {
     pub const CONTROL_TRB_TYPE_SHIFT: u32 = 10;
     pub const CONTROL_TRB_TYPE_MASK: u32 = 0x3f << Self::CONTROL_TRB_TYPE_SHIFT;
 
+    /// Isoch TRB "Start Isoch ASAP" (SIA) bit.
+    ///
+    /// Reference: xHCI 1.2 §6.4.1.3 "Isoch TRB".
+    pub const CONTROL_ISOCH_SIA_BIT: u32 = 1 << 31;
+    /// Isoch TRB Frame ID field (bits 20..=30).
+    pub const CONTROL_ISOCH_FRAME_ID_SHIFT: u32 = 20;
+    pub const CONTROL_ISOCH_FRAME_ID_MASK: u32 = 0x7ff << Self::CONTROL_ISOCH_FRAME_ID_SHIFT;
+
     /// Address Device Command TRB "Block Set Address Request" (BSR) bit.
     ///
     /// Reference: xHCI 1.2 §6.4.3.4 "Address Device Command TRB".
@@ -314,6 +328,32 @@ impl Trb {
         (self.control & Self::CONTROL_IOC_BIT) != 0
     }
 
+    /// For Isoch TRBs, returns whether the TD should be scheduled as soon as possible (SIA bit)
+    /// rather than in the frame named by [`Trb::isoch_frame_id`].
+    #[inline]
+    pub const fn isoch_sia(&self) -> bool {
+        (self.control & Self::CONTROL_ISOCH_SIA_BIT) != 0
+    }
+
+    #[inline]
+    pub fn set_isoch_sia(&mut self, on: bool) {
+        self.control = (self.control & !Self::CONTROL_ISOCH_SIA_BIT) | ((on as u32) << 31);
+    }
+
+    /// For Isoch TRBs, returns the 11-bit target frame number (`MFINDEX >> 3` modulo 2048).
+    #[inline]
+    pub const fn isoch_frame_id(&self) -> u16 {
+        ((self.control & Self::CONTROL_ISOCH_FRAME_ID_MASK) >> Self::CONTROL_ISOCH_FRAME_ID_SHIFT)
+            as u16
+    }
+
+    #[inline]
+    pub fn set_isoch_frame_id(&mut self, frame_id: u16) {
+        let frame_id = (frame_id as u32) & 0x7ff;
+        self.control = (self.control & !Self::CONTROL_ISOCH_FRAME_ID_MASK)
+            | (frame_id << Self::CONTROL_ISOCH_FRAME_ID_SHIFT);
+    }
+
     /// For transfer TRBs, returns the Transfer Length field.
     #[inline]
     pub const fn transfer_len(&self) -> u32 {
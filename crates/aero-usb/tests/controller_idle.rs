@@ -0,0 +1,61 @@
+use aero_usb::ehci::regs::{reg_portsc, CONFIGFLAG_CF, PORTSC_PP, PORTSC_PR, REG_CONFIGFLAG};
+use aero_usb::ehci::EhciController;
+use aero_usb::hid::keyboard::UsbHidKeyboardHandle;
+use aero_usb::uhci::regs::{REG_USBCMD, USBCMD_RS};
+use aero_usb::uhci::UhciController;
+
+mod util;
+
+use util::TestMemory;
+
+#[test]
+fn uhci_is_idle_only_while_halted_with_quiet_ports() {
+    let mut mem = TestMemory::new(0x1000);
+    let mut uhci = UhciController::new();
+    assert!(uhci.is_idle());
+
+    uhci.io_write(REG_USBCMD, 2, u32::from(USBCMD_RS));
+    assert!(!uhci.is_idle());
+    uhci.io_write(REG_USBCMD, 2, 0);
+    assert!(uhci.is_idle());
+
+    // A connected but disabled port does not tick its device.
+    uhci.hub_mut()
+        .attach(0, Box::new(UsbHidKeyboardHandle::new()));
+    assert!(uhci.is_idle());
+
+    // The reset timer runs while PR is set; afterwards the enabled device ticks every frame.
+    const PORTSC_PR: u16 = 1 << 9;
+    uhci.hub_mut().write_portsc(0, PORTSC_PR);
+    assert!(!uhci.is_idle());
+    for _ in 0..50 {
+        uhci.tick_1ms(&mut mem);
+    }
+    assert!(!uhci.is_idle());
+
+    uhci.hub_mut().detach(0);
+    assert!(uhci.is_idle());
+}
+
+#[test]
+fn ehci_is_idle_only_while_halted_with_quiet_ports() {
+    let mut mem = TestMemory::new(0x1000);
+    let mut ehci = EhciController::new_with_port_count(1);
+    assert!(ehci.is_idle());
+
+    ehci.hub_mut()
+        .attach(0, Box::new(UsbHidKeyboardHandle::new()));
+    // The attach latched a port change that the next tick folds into USBSTS.PCD.
+    assert!(!ehci.is_idle());
+    ehci.tick_1ms(&mut mem);
+    assert!(ehci.is_idle());
+
+    ehci.mmio_write(REG_CONFIGFLAG, 4, CONFIGFLAG_CF);
+    ehci.mmio_write(reg_portsc(0), 4, PORTSC_PP | PORTSC_PR);
+    assert!(!ehci.is_idle());
+    for _ in 0..50 {
+        ehci.tick_1ms(&mut mem);
+    }
+    // The port is enabled now, so its keyboard ticks every frame.
+    assert!(!ehci.is_idle());
+}
@@ -0,0 +1,160 @@
+use aero_io_snapshot::io::state::IoSnapshot;
+use aero_usb::passthrough::UsbHostAction;
+use aero_usb::{
+    ControlResponse, ProxyUsbDescriptors, ProxyUsbDevice, ProxyUsbResult, SetupPacket,
+    UsbDeviceModel, UsbInResult, UsbOutResult, UsbSpeed,
+};
+
+const DEVICE_DESCRIPTOR: [u8; 18] = [
+    18, 0x01, 0x00, 0x02, 0xff, 0x00, 0x00, 64, 0x34, 0x12, 0x78, 0x56, 0x00, 0x01, 0, 0, 0, 1,
+];
+
+fn descriptors() -> ProxyUsbDescriptors {
+    ProxyUsbDescriptors {
+        speed: UsbSpeed::High,
+        device: DEVICE_DESCRIPTOR.to_vec(),
+        configuration: vec![9, 0x02, 9, 0, 0, 1, 0, 0x80, 50],
+        strings: vec![vec![4, 0x03, 0x09, 0x04]],
+    }
+}
+
+fn get_descriptor(desc_type: u8, index: u8, len: u16) -> SetupPacket {
+    SetupPacket {
+        bm_request_type: 0x80,
+        b_request: 0x06,
+        w_value: u16::from_le_bytes([index, desc_type]),
+        w_index: 0,
+        w_length: len,
+    }
+}
+
+#[test]
+fn proxy_device_answers_registered_descriptors_locally() {
+    let mut dev = ProxyUsbDevice::new(descriptors());
+    assert_eq!(dev.speed(), UsbSpeed::High);
+
+    assert_eq!(
+        dev.handle_control_request(get_descriptor(0x01, 0, 8), None),
+        ControlResponse::Data(DEVICE_DESCRIPTOR[..8].to_vec())
+    );
+    assert_eq!(
+        dev.handle_control_request(get_descriptor(0x03, 0, 255), None),
+        ControlResponse::Data(vec![4, 0x03, 0x09, 0x04])
+    );
+    assert!(dev.take_requests().is_empty());
+
+    // Unregistered string descriptors are forwarded to the host.
+    assert_eq!(
+        dev.handle_control_request(get_descriptor(0x03, 1, 255), None),
+        ControlResponse::Nak
+    );
+    assert_eq!(dev.take_requests().len(), 1);
+}
+
+#[test]
+fn proxy_device_naks_until_host_completes_transfer() {
+    let mut dev = ProxyUsbDevice::new(descriptors());
+    let vendor_in = SetupPacket {
+        bm_request_type: 0xc0,
+        b_request: 0x01,
+        w_value: 0,
+        w_index: 0,
+        w_length: 4,
+    };
+
+    assert_eq!(
+        dev.handle_control_request(vendor_in, None),
+        ControlResponse::Nak
+    );
+    let reqs = dev.take_requests();
+    assert_eq!(reqs.len(), 1);
+    let UsbHostAction::ControlIn { id: ctrl_id, .. } = reqs[0] else {
+        panic!("expected ControlIn, got {:?}", reqs[0]);
+    };
+
+    assert_eq!(dev.handle_in_transfer(0x81, 8), UsbInResult::Nak);
+    assert_eq!(dev.handle_out_transfer(0x02, &[1, 2, 3]), UsbOutResult::Nak);
+    let reqs = dev.take_requests();
+    assert_eq!(reqs.len(), 2);
+    let ids: Vec<u32> = reqs
+        .iter()
+        .map(|req| match req {
+            UsbHostAction::BulkIn { id, endpoint, .. } => {
+                assert_eq!(*endpoint, 0x81);
+                *id
+            }
+            UsbHostAction::BulkOut { id, endpoint, data } => {
+                assert_eq!(*endpoint, 0x02);
+                assert_eq!(data, &[1, 2, 3]);
+                *id
+            }
+            other => panic!("unexpected request {other:?}"),
+        })
+        .collect();
+    assert_eq!(dev.outstanding_requests(), 3);
+
+    // Retries while outstanding keep NAKing and do not emit duplicate requests.
+    assert_eq!(
+        dev.handle_control_request(vendor_in, None),
+        ControlResponse::Nak
+    );
+    assert_eq!(dev.handle_in_transfer(0x81, 8), UsbInResult::Nak);
+    assert!(dev.take_requests().is_empty());
+
+    assert!(dev.complete(ctrl_id, ProxyUsbResult::Data(vec![0xaa, 0xbb])));
+    assert!(dev.complete(ids[0], ProxyUsbResult::Data(vec![1, 2, 3, 4])));
+    assert!(dev.complete(ids[1], ProxyUsbResult::Stall));
+    assert!(!dev.complete(ids[1], ProxyUsbResult::Stall));
+    assert_eq!(dev.outstanding_requests(), 0);
+
+    assert_eq!(
+        dev.handle_control_request(vendor_in, None),
+        ControlResponse::Data(vec![0xaa, 0xbb])
+    );
+    assert_eq!(
+        dev.handle_in_transfer(0x81, 8),
+        UsbInResult::Data(vec![1, 2, 3, 4])
+    );
+    assert_eq!(
+        dev.handle_out_transfer(0x02, &[1, 2, 3]),
+        UsbOutResult::Stall
+    );
+}
+
+#[test]
+fn proxy_device_detach_stalls_outstanding_requests() {
+    let mut dev = ProxyUsbDevice::new(descriptors());
+    assert_eq!(dev.handle_in_transfer(0x81, 8), UsbInResult::Nak);
+    let reqs = dev.take_requests();
+    let UsbHostAction::BulkIn { id, .. } = reqs[0] else {
+        panic!("expected BulkIn, got {:?}", reqs[0]);
+    };
+
+    dev.detach();
+    assert!(dev.is_detached());
+    assert_eq!(dev.outstanding_requests(), 0);
+    assert!(!dev.complete(id, ProxyUsbResult::Data(vec![1])));
+    assert_eq!(dev.handle_in_transfer(0x81, 8), UsbInResult::Stall);
+    assert!(dev.take_requests().is_empty());
+}
+
+#[test]
+fn proxy_device_snapshot_keeps_descriptors_but_drops_inflight_transfers() {
+    let mut dev = ProxyUsbDevice::new(descriptors());
+    assert_eq!(dev.handle_in_transfer(0x81, 8), UsbInResult::Nak);
+    assert_eq!(dev.take_requests().len(), 1);
+
+    let snap = dev.save_state();
+
+    let mut restored = ProxyUsbDevice::new(ProxyUsbDescriptors {
+        device: vec![18, 0x01],
+        ..ProxyUsbDescriptors::default()
+    });
+    restored.load_state(&snap).unwrap();
+    assert_eq!(restored.descriptors(), descriptors());
+    assert_eq!(restored.outstanding_requests(), 0);
+
+    // The guest's retried TD re-emits a fresh host request instead of waiting forever.
+    assert_eq!(restored.handle_in_transfer(0x81, 8), UsbInResult::Nak);
+    assert_eq!(restored.take_requests().len(), 1);
+}
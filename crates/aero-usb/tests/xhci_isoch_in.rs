@@ -0,0 +1,260 @@
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use aero_usb::xhci::context::{SlotContext, CONTEXT_SIZE};
+use aero_usb::xhci::trb::{CompletionCode, Trb, TrbType, TRB_LEN};
+use aero_usb::xhci::{CommandCompletionCode, XhciController};
+use aero_usb::{ControlResponse, MemoryBus, SetupPacket, UsbDeviceModel, UsbInResult};
+
+mod util;
+
+use util::{xhci_set_run, Alloc, TestMemory};
+
+/// Endpoint 1 IN => endpoint id 3.
+const EP_ID: u8 = 3;
+const PACKET_LEN: u32 = 8;
+/// Frame in which the synthetic device only produces a partial packet.
+const SHORT_FRAME: u64 = 3;
+const SHORT_LEN: usize = 5;
+
+fn pattern(frame: u64, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| (frame as u8).wrapping_mul(0x10).wrapping_add(i as u8))
+        .collect()
+}
+
+/// Synthetic isochronous IN device producing a frame-derived byte pattern.
+#[derive(Clone, Debug)]
+struct IsochInDevice {
+    serviced_frames: Rc<RefCell<Vec<u64>>>,
+}
+
+impl UsbDeviceModel for IsochInDevice {
+    fn handle_control_request(
+        &mut self,
+        _setup: SetupPacket,
+        _data_stage: Option<&[u8]>,
+    ) -> ControlResponse {
+        ControlResponse::Ack
+    }
+
+    fn handle_in_transfer(&mut self, _ep_addr: u8, _max_len: usize) -> UsbInResult {
+        panic!("isochronous endpoints must not use the bulk/interrupt transfer path");
+    }
+
+    fn handle_isoch_in_transfer(&mut self, ep: u8, frame: u64, max_len: usize) -> UsbInResult {
+        assert_eq!(ep, 0x81);
+        self.serviced_frames.borrow_mut().push(frame);
+        let len = if frame == SHORT_FRAME {
+            SHORT_LEN
+        } else {
+            max_len
+        };
+        UsbInResult::Data(pattern(frame, len))
+    }
+}
+
+fn configure_dcbaa(mem: &mut TestMemory, dcbaa: u64, slot_id: u8, dev_ctx_ptr: u64) {
+    MemoryBus::write_u64(mem, dcbaa + u64::from(slot_id) * 8, dev_ctx_ptr);
+}
+
+fn write_isoch_in_endpoint_context(
+    mem: &mut TestMemory,
+    dev_ctx_base: u64,
+    endpoint_id: u8,
+    ring_base: u64,
+) {
+    let base = dev_ctx_base + u64::from(endpoint_id) * (CONTEXT_SIZE as u64);
+    // Endpoint state: Running (1).
+    MemoryBus::write_u32(mem, base, 1);
+    // Endpoint type (Isoch IN = 5) + max packet size.
+    let dw1 = (5u32 << 3) | (PACKET_LEN << 16);
+    MemoryBus::write_u32(mem, base + 4, dw1);
+
+    let tr_dequeue_raw = (ring_base & !0x0f) | 1;
+    MemoryBus::write_u32(mem, base + 8, tr_dequeue_raw as u32);
+    MemoryBus::write_u32(mem, base + 12, (tr_dequeue_raw >> 32) as u32);
+}
+
+fn make_isoch_trb(buf_ptr: u64, len: u32, frame_id: Option<u16>) -> Trb {
+    let mut trb = Trb::new(buf_ptr, len & Trb::STATUS_TRANSFER_LEN_MASK, 0);
+    trb.set_trb_type(TrbType::Isoch);
+    trb.set_cycle(true);
+    trb.control |= Trb::CONTROL_IOC_BIT;
+    match frame_id {
+        Some(frame_id) => trb.set_isoch_frame_id(frame_id),
+        None => trb.set_isoch_sia(true),
+    }
+    trb
+}
+
+fn drain_events(ctrl: &mut XhciController) -> Vec<Trb> {
+    let mut out = Vec::new();
+    while let Some(ev) = ctrl.pop_pending_event() {
+        out.push(ev);
+    }
+    out
+}
+
+fn assert_transfer_event(ev: &Trb, slot_id: u8, trb_ptr: u64, code: CompletionCode, residual: u32) {
+    assert_eq!(ev.trb_type(), TrbType::TransferEvent);
+    assert_eq!(ev.slot_id(), slot_id);
+    assert_eq!(ev.endpoint_id(), EP_ID);
+    assert_eq!(ev.parameter, trb_ptr);
+    assert_eq!(ev.completion_code_raw(), code.as_u8(), "{code}");
+    assert_eq!(ev.status & 0x00ff_ffff, residual);
+}
+
+#[test]
+fn xhci_isoch_in_schedules_tds_by_frame_and_reports_ring_overrun() {
+    let mut mem = TestMemory::new(0x40_000);
+    let mut alloc = Alloc::new(0x1000);
+
+    let dcbaa = alloc.alloc(0x200, 0x40) as u64;
+    let dev_ctx = alloc.alloc(0x400, 0x40) as u64;
+    let ring_base = alloc.alloc((TRB_LEN as u32) * 4, 0x10) as u64;
+    let bufs: Vec<u64> = (0..3)
+        .map(|_| alloc.alloc(PACKET_LEN, 0x10) as u64)
+        .collect();
+    let trb_ptr = |idx: u64| ring_base + idx * TRB_LEN as u64;
+
+    write_isoch_in_endpoint_context(&mut mem, dev_ctx, EP_ID, ring_base);
+    // TD0: Start Isoch ASAP.
+    make_isoch_trb(bufs[0], PACKET_LEN, None).write_to(&mut mem, trb_ptr(0));
+    // TD1: scheduled for frame 3; the device only produces a partial packet in that frame.
+    make_isoch_trb(bufs[1], PACKET_LEN, Some(SHORT_FRAME as u16)).write_to(&mut mem, trb_ptr(1));
+    // TD2: scheduled for frame 1, which has already passed by the time it reaches the head of the
+    // ring.
+    make_isoch_trb(bufs[2], PACKET_LEN, Some(1)).write_to(&mut mem, trb_ptr(2));
+
+    let serviced_frames = Rc::new(RefCell::new(Vec::new()));
+    let mut ctrl = XhciController::new();
+    ctrl.attach_device(
+        0,
+        Box::new(IsochInDevice {
+            serviced_frames: serviced_frames.clone(),
+        }),
+    );
+    while ctrl.pop_pending_event().is_some() {}
+    xhci_set_run(&mut ctrl);
+    ctrl.set_dcbaap(dcbaa);
+    let enable = ctrl.enable_slot(&mut mem);
+    assert_eq!(enable.completion_code, CommandCompletionCode::Success);
+    let slot_id = enable.slot_id;
+    configure_dcbaa(&mut mem, dcbaa, slot_id, dev_ctx);
+
+    let mut slot_ctx = SlotContext::default();
+    slot_ctx.set_root_hub_port_number(1);
+    let addr = ctrl.address_device(slot_id, slot_ctx);
+    assert_eq!(addr.completion_code, CommandCompletionCode::Success);
+
+    ctrl.ring_doorbell(slot_id, EP_ID);
+
+    let mut events_by_frame = Vec::new();
+    for _ in 0..7 {
+        ctrl.tick(&mut mem);
+        events_by_frame.push(drain_events(&mut ctrl));
+        ctrl.tick_1ms_no_dma();
+    }
+
+    // Frame 0: SIA TD runs immediately.
+    assert_eq!(events_by_frame[0].len(), 1);
+    assert_transfer_event(
+        &events_by_frame[0][0],
+        slot_id,
+        trb_ptr(0),
+        CompletionCode::Success,
+        0,
+    );
+    // Frames 1-2: TD1 is scheduled in the future; nothing happens.
+    assert!(events_by_frame[1].is_empty());
+    assert!(events_by_frame[2].is_empty());
+    // Frame 3: TD1 runs and completes short.
+    assert_eq!(events_by_frame[3].len(), 1);
+    assert_transfer_event(
+        &events_by_frame[3][0],
+        slot_id,
+        trb_ptr(1),
+        CompletionCode::ShortPacket,
+        PACKET_LEN - SHORT_LEN as u32,
+    );
+    // Frame 4: TD2's frame has passed; it is retired without touching the device.
+    assert_eq!(events_by_frame[4].len(), 1);
+    assert_transfer_event(
+        &events_by_frame[4][0],
+        slot_id,
+        trb_ptr(2),
+        CompletionCode::MissedServiceError,
+        PACKET_LEN,
+    );
+    // Frame 5: the ring is empty at the next service interval (IN => Ring Overrun, no TRB pointer).
+    assert_eq!(events_by_frame[5].len(), 1);
+    assert_transfer_event(
+        &events_by_frame[5][0],
+        slot_id,
+        0,
+        CompletionCode::RingOverrun,
+        0,
+    );
+    // Frame 6: the overrun is reported once; the endpoint idles until the next doorbell.
+    assert!(events_by_frame[6].is_empty());
+
+    assert_eq!(*serviced_frames.borrow(), vec![0, SHORT_FRAME]);
+
+    let mut buf = [0u8; PACKET_LEN as usize];
+    mem.read_physical(bufs[0], &mut buf);
+    assert_eq!(buf.to_vec(), pattern(0, PACKET_LEN as usize));
+    mem.read_physical(bufs[1], &mut buf);
+    assert_eq!(
+        &buf[..SHORT_LEN],
+        pattern(SHORT_FRAME, SHORT_LEN).as_slice()
+    );
+    assert_eq!(&buf[SHORT_LEN..], &[0u8; PACKET_LEN as usize - SHORT_LEN]);
+    mem.read_physical(bufs[2], &mut buf);
+    assert_eq!(buf, [0u8; PACKET_LEN as usize]);
+}
+
+#[test]
+fn xhci_isoch_endpoint_rejects_td_not_starting_with_isoch_trb() {
+    let mut mem = TestMemory::new(0x40_000);
+    let mut alloc = Alloc::new(0x1000);
+
+    let dcbaa = alloc.alloc(0x200, 0x40) as u64;
+    let dev_ctx = alloc.alloc(0x400, 0x40) as u64;
+    let ring_base = alloc.alloc((TRB_LEN as u32) * 2, 0x10) as u64;
+    let buf_ptr = alloc.alloc(PACKET_LEN, 0x10) as u64;
+
+    write_isoch_in_endpoint_context(&mut mem, dev_ctx, EP_ID, ring_base);
+    let mut normal = Trb::new(buf_ptr, PACKET_LEN, 0);
+    normal.set_trb_type(TrbType::Normal);
+    normal.set_cycle(true);
+    normal.control |= Trb::CONTROL_IOC_BIT;
+    normal.write_to(&mut mem, ring_base);
+
+    let serviced_frames = Rc::new(RefCell::new(Vec::new()));
+    let mut ctrl = XhciController::new();
+    ctrl.attach_device(
+        0,
+        Box::new(IsochInDevice {
+            serviced_frames: serviced_frames.clone(),
+        }),
+    );
+    while ctrl.pop_pending_event().is_some() {}
+    xhci_set_run(&mut ctrl);
+    ctrl.set_dcbaap(dcbaa);
+    let enable = ctrl.enable_slot(&mut mem);
+    let slot_id = enable.slot_id;
+    configure_dcbaa(&mut mem, dcbaa, slot_id, dev_ctx);
+    let mut slot_ctx = SlotContext::default();
+    slot_ctx.set_root_hub_port_number(1);
+    ctrl.address_device(slot_id, slot_ctx);
+
+    ctrl.ring_doorbell(slot_id, EP_ID);
+    ctrl.tick(&mut mem);
+
+    let events = drain_events(&mut ctrl);
+    assert_eq!(events.len(), 1);
+    assert_transfer_event(&events[0], slot_id, ring_base, CompletionCode::TrbError, 0);
+    assert!(serviced_frames.borrow().is_empty());
+}
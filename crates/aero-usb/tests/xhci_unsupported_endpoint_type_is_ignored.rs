@@ -35,7 +35,7 @@ fn endpoint_ctx_addr(dev_ctx_base: u64, endpoint_id: u8) -> u64 {
     dev_ctx_base + u64::from(endpoint_id) * (CONTEXT_SIZE as u64)
 }
 
-fn write_isoch_in_endpoint_context(
+fn write_control_endpoint_context(
     mem: &mut TestMemory,
     dev_ctx_base: u64,
     endpoint_id: u8,
@@ -44,8 +44,8 @@ fn write_isoch_in_endpoint_context(
     let base = endpoint_ctx_addr(dev_ctx_base, endpoint_id);
     // Endpoint state: Running (1).
     MemoryBus::write_u32(mem, base, 1);
-    // Endpoint type (Isoch IN = 5) + max packet size.
-    let dw1 = (5u32 << 3) | (8u32 << 16);
+    // Endpoint type (Control = 4) + max packet size. Control endpoints are only valid at DCI 1.
+    let dw1 = (4u32 << 3) | (8u32 << 16);
     MemoryBus::write_u32(mem, base + 4, dw1);
 
     let tr_dequeue_raw = (ring_base & !0x0f) | 1;
@@ -76,10 +76,10 @@ fn xhci_does_not_execute_transfers_for_unsupported_endpoint_types() {
     // Endpoint 1 IN => endpoint id 3.
     const EP_ID: u8 = 3;
 
-    // Guest context describes an Isochronous endpoint, which the current transfer executor does
-    // not support. Even if controller-local ring cursors exist (e.g. from a malformed snapshot),
-    // the controller must not execute transfers for the endpoint.
-    write_isoch_in_endpoint_context(&mut mem, dev_ctx, EP_ID, ring_base);
+    // Guest context describes a Control endpoint at a non-zero endpoint number, which the transfer
+    // executor does not support. Even if controller-local ring cursors exist (e.g. from a
+    // malformed snapshot), the controller must not execute transfers for the endpoint.
+    write_control_endpoint_context(&mut mem, dev_ctx, EP_ID, ring_base);
     make_normal_trb(buf_ptr, 8, true, true).write_to(&mut mem, ring_base);
 
     let mut ctrl = XhciController::new();
@@ -0,0 +1,207 @@
+use aero_usb::xhci::interrupter::{IMAN_IE, IMAN_IP};
+use aero_usb::xhci::trb::{Trb, TrbType, TRB_LEN};
+use aero_usb::xhci::{regs, XhciController, PORTSC_CCS, PORTSC_CSC, PORTSC_PED, PORTSC_PR};
+use aero_usb::{
+    ControlResponse, MemoryBus, SetupPacket, UsbDeviceModel, UsbHubAttachError, UsbSpeed,
+};
+
+mod util;
+
+use util::TestMemory;
+
+const USB2_PORTS: u8 = 2;
+const USB3_PORTS: u8 = 2;
+
+struct SpeedDevice(UsbSpeed);
+
+impl UsbDeviceModel for SpeedDevice {
+    fn speed(&self) -> UsbSpeed {
+        self.0
+    }
+
+    fn handle_control_request(
+        &mut self,
+        _setup: SetupPacket,
+        _data_stage: Option<&[u8]>,
+    ) -> ControlResponse {
+        ControlResponse::Stall
+    }
+}
+
+fn pls(portsc: u32) -> u8 {
+    ((portsc & regs::PORTSC_PLS_MASK) >> regs::PORTSC_PLS_SHIFT) as u8
+}
+
+fn port_speed(portsc: u32) -> u8 {
+    ((portsc & regs::PORTSC_PS_MASK) >> regs::PORTSC_PS_SHIFT) as u8
+}
+
+fn read_portsc(xhci: &mut XhciController, port: usize) -> u32 {
+    xhci.mmio_read(regs::port::portsc_offset(port), 4) as u32
+}
+
+fn write_erst_entry(mem: &mut TestMemory, erstba: u64, seg_base: u64, seg_size_trbs: u32) {
+    MemoryBus::write_u64(mem, erstba, seg_base);
+    MemoryBus::write_u32(mem, erstba + 8, seg_size_trbs);
+    MemoryBus::write_u32(mem, erstba + 12, 0);
+}
+
+fn configure_event_ring(xhci: &mut XhciController, mem: &mut TestMemory, ring_base: u64) {
+    let erstba = 0x1000u64;
+    write_erst_entry(mem, erstba, ring_base, 8);
+    xhci.mmio_write(regs::REG_INTR0_ERSTSZ, 4, 1);
+    xhci.mmio_write(regs::REG_INTR0_ERSTBA_LO, 4, erstba);
+    xhci.mmio_write(regs::REG_INTR0_ERSTBA_HI, 4, erstba >> 32);
+    xhci.mmio_write(regs::REG_INTR0_ERDP_LO, 4, ring_base);
+    xhci.mmio_write(regs::REG_INTR0_ERDP_HI, 4, ring_base >> 32);
+    xhci.mmio_write(regs::REG_INTR0_IMAN, 4, u64::from(IMAN_IE));
+}
+
+/// Reads the PSC event at `index` and returns its 1-based port ID.
+fn drain_psc_event(
+    xhci: &mut XhciController,
+    mem: &mut TestMemory,
+    ring_base: u64,
+    index: u64,
+) -> u8 {
+    xhci.service_event_ring(mem);
+    let ev = Trb::read_from(mem, ring_base + index * (TRB_LEN as u64));
+    assert!(ev.cycle());
+    assert_eq!(ev.trb_type(), TrbType::PortStatusChangeEvent);
+    xhci.mmio_write(regs::REG_INTR0_IMAN, 4, u64::from(IMAN_IP | IMAN_IE));
+    ((ev.parameter >> regs::PSC_EVENT_PORT_ID_SHIFT) & 0xff) as u8
+}
+
+fn find_supported_protocol_caps(xhci: &mut XhciController) -> Vec<u64> {
+    let hccparams1 = xhci.mmio_read_u32(regs::cap::HCCPARAMS1 as u64);
+    let mut off = u64::from((hccparams1 >> 16) & 0xffff) * 4;
+    let mut caps = Vec::new();
+    for _ in 0..32 {
+        if off == 0 {
+            break;
+        }
+        let cap0 = xhci.mmio_read_u32(off);
+        if (cap0 & 0xff) as u8 == regs::EXT_CAP_ID_SUPPORTED_PROTOCOL {
+            caps.push(off);
+        }
+        off = u64::from((cap0 >> 8) & 0xff) * 4;
+    }
+    caps
+}
+
+#[test]
+fn supported_protocol_caps_split_usb2_and_usb3_ports() {
+    let mut xhci = XhciController::with_port_layout(USB2_PORTS, USB3_PORTS);
+    assert_eq!(xhci.port_count(), USB2_PORTS + USB3_PORTS);
+    assert!(!xhci.port_is_usb3(usize::from(USB2_PORTS) - 1));
+    assert!(xhci.port_is_usb3(usize::from(USB2_PORTS)));
+
+    let caps = find_supported_protocol_caps(&mut xhci);
+    assert_eq!(
+        caps.len(),
+        2,
+        "expected one USB 2.0 and one USB 3.0 capability"
+    );
+
+    // (revision, first 1-based port, port count) per capability.
+    let ranges: Vec<_> = caps
+        .iter()
+        .map(|&off| {
+            let rev = (xhci.mmio_read_u32(off) >> 16) as u16;
+            let ports = xhci.mmio_read_u32(off + 8);
+            (rev, (ports & 0xff) as u8, ((ports >> 8) & 0xff) as u8)
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (regs::USB_REVISION_2_0, 1, USB2_PORTS),
+            (regs::USB_REVISION_3_0, USB2_PORTS + 1, USB3_PORTS),
+        ]
+    );
+
+    // The USB 3.0 capability defines exactly the SuperSpeed PSI.
+    let usb3 = caps[1];
+    let dword3 = xhci.mmio_read_u32(usb3 + 12);
+    assert_eq!(dword3 & 0xf, 1, "PSIC");
+    let psio = u64::from((dword3 >> 16) & 0xffff);
+    let psi = xhci.mmio_read_u32(usb3 + psio * 4);
+    assert_eq!((psi & 0xf) as u8, regs::PSIV_SUPER_SPEED);
+}
+
+#[test]
+fn usb3_port_trains_link_to_u0_and_reports_superspeed() {
+    let mut xhci = XhciController::with_port_layout(USB2_PORTS, USB3_PORTS);
+    let mut mem = TestMemory::new(0x20_000);
+    let ring_base = 0x2000u64;
+    configure_event_ring(&mut xhci, &mut mem, ring_base);
+
+    let port = usize::from(USB2_PORTS);
+    let portsc = read_portsc(&mut xhci, port);
+    assert_eq!(pls(portsc), regs::PLS_RX_DETECT);
+    assert_eq!(portsc & PORTSC_CCS, 0);
+
+    xhci.attach_at_path(&[USB2_PORTS], Box::new(SpeedDevice(UsbSpeed::Super)))
+        .unwrap();
+
+    // Link training: the connection is not reported until the link reaches U0.
+    let portsc = read_portsc(&mut xhci, port);
+    assert_eq!(pls(portsc), regs::PLS_POLLING);
+    assert_eq!(portsc & (PORTSC_CCS | PORTSC_CSC), 0);
+    assert_eq!(xhci.pending_event_count(), 0);
+
+    for _ in 0..10 {
+        xhci.tick_1ms(&mut mem);
+    }
+    let portsc = read_portsc(&mut xhci, port);
+    assert_eq!(pls(portsc), regs::PLS_U0);
+    assert_ne!(portsc & PORTSC_CCS, 0);
+    assert_ne!(portsc & PORTSC_CSC, 0);
+    // USB3 ports enable themselves; no host-driven port reset is needed.
+    assert_ne!(portsc & PORTSC_PED, 0);
+    assert_eq!(portsc & PORTSC_PR, 0);
+    assert_eq!(port_speed(portsc), regs::PSIV_SUPER_SPEED);
+
+    assert_eq!(
+        drain_psc_event(&mut xhci, &mut mem, ring_base, 0),
+        USB2_PORTS + 1
+    );
+
+    // Clear CSC, then unplug: the port drops back to RxDetect and reports the disconnect.
+    xhci.mmio_write(regs::port::portsc_offset(port), 4, u64::from(PORTSC_CSC));
+    xhci.detach_at_path(&[USB2_PORTS]).unwrap();
+    let portsc = read_portsc(&mut xhci, port);
+    assert_eq!(pls(portsc), regs::PLS_RX_DETECT);
+    assert_eq!(portsc & (PORTSC_CCS | PORTSC_PED), 0);
+    assert_ne!(portsc & PORTSC_CSC, 0);
+
+    xhci.tick_1ms(&mut mem);
+    assert_eq!(
+        drain_psc_event(&mut xhci, &mut mem, ring_base, 1),
+        USB2_PORTS + 1
+    );
+}
+
+#[test]
+fn port_kind_decides_which_devices_connect_and_at_what_speed() {
+    let mut xhci = XhciController::with_port_layout(USB2_PORTS, USB3_PORTS);
+
+    // USB3 ports only detect SuperSpeed receivers.
+    assert_eq!(
+        xhci.attach_at_path(&[USB2_PORTS], Box::new(SpeedDevice(UsbSpeed::High))),
+        Err(UsbHubAttachError::InvalidPort)
+    );
+    assert!(xhci.port_device(usize::from(USB2_PORTS)).is_none());
+
+    // A SuperSpeed device on a USB2 port falls back to high-speed.
+    xhci.attach_at_path(&[0], Box::new(SpeedDevice(UsbSpeed::Super)))
+        .unwrap();
+    let portsc = read_portsc(&mut xhci, 0);
+    assert_ne!(portsc & PORTSC_CCS, 0);
+    assert_eq!(port_speed(portsc), regs::PSIV_HIGH_SPEED);
+
+    // Full-speed devices keep working on USB2 ports.
+    xhci.attach_at_path(&[1], Box::new(SpeedDevice(UsbSpeed::Full)))
+        .unwrap();
+    assert_eq!(port_speed(read_portsc(&mut xhci, 1)), regs::PSIV_FULL_SPEED);
+}
@@ -0,0 +1,376 @@
+use crate::devices::{VirtioDevice, VirtioDeviceError};
+use crate::memory::GuestMemory;
+use crate::pci::{VIRTIO_F_RING_INDIRECT_DESC, VIRTIO_F_VERSION_1};
+use crate::queue::{DescriptorChain, VirtQueue};
+use std::collections::VecDeque;
+
+pub const VIRTIO_DEVICE_TYPE_BALLOON: u16 = 5;
+
+pub const VIRTIO_BALLOON_F_MUST_TELL_HOST: u64 = 1 << 0;
+pub const VIRTIO_BALLOON_F_STATS_VQ: u64 = 1 << 1;
+pub const VIRTIO_BALLOON_F_DEFLATE_ON_OOM: u64 = 1 << 2;
+
+/// Balloon PFNs always refer to 4KiB pages, regardless of the guest's page size.
+pub const VIRTIO_BALLOON_PFN_SHIFT: u32 = 12;
+pub const VIRTIO_BALLOON_PAGE_SIZE: u64 = 1 << VIRTIO_BALLOON_PFN_SHIFT;
+
+pub const VIRTIO_BALLOON_S_SWAP_IN: u16 = 0;
+pub const VIRTIO_BALLOON_S_SWAP_OUT: u16 = 1;
+pub const VIRTIO_BALLOON_S_MAJFLT: u16 = 2;
+pub const VIRTIO_BALLOON_S_MINFLT: u16 = 3;
+pub const VIRTIO_BALLOON_S_MEMFREE: u16 = 4;
+pub const VIRTIO_BALLOON_S_MEMTOT: u16 = 5;
+pub const VIRTIO_BALLOON_S_AVAIL: u16 = 6;
+pub const VIRTIO_BALLOON_S_CACHES: u16 = 7;
+pub const VIRTIO_BALLOON_S_HTLB_PGALLOC: u16 = 8;
+pub const VIRTIO_BALLOON_S_HTLB_PGFAIL: u16 = 9;
+
+/// Virtqueue indices.
+pub const VIRTIO_BALLOON_QUEUE_INFLATE: u16 = 0;
+pub const VIRTIO_BALLOON_QUEUE_DEFLATE: u16 = 1;
+pub const VIRTIO_BALLOON_QUEUE_STATS: u16 = 2;
+
+// Host-side safety: cap how many PFN bytes we read from a single inflate/deflate chain. Linux
+// posts at most 256 PFNs per chain; anything far beyond that is treated as malformed.
+const MAX_PFN_BYTES_PER_CHAIN: usize = 64 * 1024;
+
+// Host-side safety: cap how many stats tags we parse per buffer (the spec defines 10).
+const MAX_STATS_ENTRIES: usize = 64;
+
+// Host-side safety: cap buffered page events if the platform never drains them.
+const MAX_PENDING_EVENTS: usize = 1024;
+
+/// A batch of guest page frames handed to (inflate) or reclaimed from (deflate) the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VirtioBalloonEvent {
+    /// The guest gave up these 4KiB PFNs; the host may discard their contents.
+    Inflate(Vec<u32>),
+    /// The guest took these 4KiB PFNs back.
+    Deflate(Vec<u32>),
+}
+
+/// Guest memory statistics reported through the stats virtqueue.
+///
+/// Fields are `None` when the guest driver did not report the corresponding tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VirtioBalloonStats {
+    pub swap_in: Option<u64>,
+    pub swap_out: Option<u64>,
+    pub major_faults: Option<u64>,
+    pub minor_faults: Option<u64>,
+    /// Free memory in bytes.
+    pub free_memory: Option<u64>,
+    /// Total memory in bytes.
+    pub total_memory: Option<u64>,
+    /// Available memory in bytes.
+    pub available_memory: Option<u64>,
+    /// Disk cache size in bytes.
+    pub disk_caches: Option<u64>,
+    pub hugetlb_allocations: Option<u64>,
+    pub hugetlb_failures: Option<u64>,
+}
+
+impl VirtioBalloonStats {
+    fn set(&mut self, tag: u16, value: u64) {
+        let slot = match tag {
+            VIRTIO_BALLOON_S_SWAP_IN => &mut self.swap_in,
+            VIRTIO_BALLOON_S_SWAP_OUT => &mut self.swap_out,
+            VIRTIO_BALLOON_S_MAJFLT => &mut self.major_faults,
+            VIRTIO_BALLOON_S_MINFLT => &mut self.minor_faults,
+            VIRTIO_BALLOON_S_MEMFREE => &mut self.free_memory,
+            VIRTIO_BALLOON_S_MEMTOT => &mut self.total_memory,
+            VIRTIO_BALLOON_S_AVAIL => &mut self.available_memory,
+            VIRTIO_BALLOON_S_CACHES => &mut self.disk_caches,
+            VIRTIO_BALLOON_S_HTLB_PGALLOC => &mut self.hugetlb_allocations,
+            VIRTIO_BALLOON_S_HTLB_PGFAIL => &mut self.hugetlb_failures,
+            // Unknown tags are ignored for forward compatibility.
+            _ => return,
+        };
+        *slot = Some(value);
+    }
+}
+
+/// Virtio memory balloon device (traditional inflate/deflate + optional stats queue).
+///
+/// The device model only tracks the balloon protocol; releasing guest RAM is up to the platform,
+/// which drains inflate/deflate batches via [`VirtioBalloon::take_events`].
+///
+/// The stats queue follows the spec's "hold one buffer" protocol: the driver posts a buffer
+/// filled with current stats, the device keeps it until the host asks for fresh numbers
+/// ([`VirtioBalloon::request_stats`]), then returns it so the driver can refill and re-post it.
+pub struct VirtioBalloon {
+    features: u64,
+    /// Host-requested balloon size in 4KiB pages (`num_pages` config field).
+    num_pages: u32,
+    /// Guest-reported balloon size in 4KiB pages (`actual` config field).
+    actual: u32,
+    events: VecDeque<VirtioBalloonEvent>,
+    stats: Option<VirtioBalloonStats>,
+    stats_buffer: Option<u16>,
+    stats_requested: bool,
+}
+
+impl Default for VirtioBalloon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtioBalloon {
+    pub fn new() -> Self {
+        Self {
+            features: 0,
+            num_pages: 0,
+            actual: 0,
+            events: VecDeque::new(),
+            stats: None,
+            stats_buffer: None,
+            stats_requested: false,
+        }
+    }
+
+    /// Host-requested balloon size in 4KiB pages.
+    pub fn num_pages(&self) -> u32 {
+        self.num_pages
+    }
+
+    /// Set the host-requested balloon size in 4KiB pages.
+    ///
+    /// Callers should raise a configuration-change interrupt afterwards
+    /// (`VirtioPciDevice::signal_config_interrupt`) so the driver notices the new target.
+    pub fn set_num_pages(&mut self, num_pages: u32) {
+        self.num_pages = num_pages;
+    }
+
+    /// Guest-reported balloon size in 4KiB pages.
+    pub fn actual(&self) -> u32 {
+        self.actual
+    }
+
+    /// Most recent statistics reported by the guest driver, if any.
+    pub fn stats(&self) -> Option<VirtioBalloonStats> {
+        self.stats
+    }
+
+    /// Ask the guest for fresh statistics.
+    ///
+    /// The held stats buffer is returned to the driver on the next poll of the stats queue; the
+    /// refreshed values become visible once the driver re-posts it.
+    pub fn request_stats(&mut self) {
+        self.stats_requested = true;
+    }
+
+    /// Drain inflate/deflate batches in the order the guest submitted them.
+    pub fn take_events(&mut self) -> Vec<VirtioBalloonEvent> {
+        self.events.drain(..).collect()
+    }
+
+    fn read_pfns(
+        chain: &DescriptorChain,
+        mem: &dyn GuestMemory,
+    ) -> Result<Vec<u32>, VirtioDeviceError> {
+        let mut bytes = Vec::new();
+        for d in chain.descriptors() {
+            if d.is_write_only() {
+                return Err(VirtioDeviceError::BadDescriptorChain);
+            }
+            let len = d.len as usize;
+            if len > MAX_PFN_BYTES_PER_CHAIN - bytes.len() {
+                return Err(VirtioDeviceError::BadDescriptorChain);
+            }
+            let start = bytes.len();
+            bytes.resize(start + len, 0);
+            mem.read(d.addr, &mut bytes[start..])
+                .map_err(|_| VirtioDeviceError::IoError)?;
+        }
+        // A trailing partial PFN is ignored.
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    fn read_stats(chain: &DescriptorChain, mem: &dyn GuestMemory) -> Option<VirtioBalloonStats> {
+        let mut bytes = Vec::new();
+        for d in chain.descriptors() {
+            if d.is_write_only() {
+                return None;
+            }
+            let remaining = MAX_STATS_ENTRIES * 10 - bytes.len();
+            let len = (d.len as usize).min(remaining);
+            let start = bytes.len();
+            bytes.resize(start + len, 0);
+            mem.read(d.addr, &mut bytes[start..]).ok()?;
+        }
+
+        let mut stats = VirtioBalloonStats::default();
+        // struct virtio_balloon_stat { le16 tag; le64 val; } (packed, 10 bytes)
+        for entry in bytes.chunks_exact(10) {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let value = u64::from_le_bytes(entry[2..10].try_into().unwrap());
+            stats.set(tag, value);
+        }
+        Some(stats)
+    }
+
+    fn push_event(&mut self, event: VirtioBalloonEvent) {
+        if self.events.len() >= MAX_PENDING_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn config_bytes(&self) -> [u8; 8] {
+        // struct virtio_balloon_config { le32 num_pages; le32 actual; ... }
+        let mut cfg = [0u8; 8];
+        cfg[0..4].copy_from_slice(&self.num_pages.to_le_bytes());
+        cfg[4..8].copy_from_slice(&self.actual.to_le_bytes());
+        cfg
+    }
+}
+
+impl VirtioDevice for VirtioBalloon {
+    fn device_type(&self) -> u16 {
+        VIRTIO_DEVICE_TYPE_BALLOON
+    }
+
+    fn device_features(&self) -> u64 {
+        VIRTIO_F_VERSION_1
+            | VIRTIO_F_RING_INDIRECT_DESC
+            | VIRTIO_BALLOON_F_STATS_VQ
+            | VIRTIO_BALLOON_F_DEFLATE_ON_OOM
+    }
+
+    fn set_features(&mut self, features: u64) {
+        self.features = features;
+    }
+
+    fn num_queues(&self) -> u16 {
+        // inflateq + deflateq + statsq.
+        3
+    }
+
+    fn queue_max_size(&self, _queue: u16) -> u16 {
+        128
+    }
+
+    fn process_queue(
+        &mut self,
+        queue_index: u16,
+        chain: DescriptorChain,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        match queue_index {
+            VIRTIO_BALLOON_QUEUE_INFLATE | VIRTIO_BALLOON_QUEUE_DEFLATE => {
+                let pfns = Self::read_pfns(&chain, &*mem)?;
+                if !pfns.is_empty() {
+                    self.push_event(if queue_index == VIRTIO_BALLOON_QUEUE_INFLATE {
+                        VirtioBalloonEvent::Inflate(pfns)
+                    } else {
+                        VirtioBalloonEvent::Deflate(pfns)
+                    });
+                }
+                queue
+                    .add_used(mem, chain.head_index(), 0)
+                    .map_err(|_| VirtioDeviceError::IoError)
+            }
+            VIRTIO_BALLOON_QUEUE_STATS => {
+                if let Some(stats) = Self::read_stats(&chain, &*mem) {
+                    self.stats = Some(stats);
+                }
+                // A well-behaved driver only ever has one stats buffer outstanding; if it posts
+                // another, return the older one rather than leaking it.
+                let mut need_irq = false;
+                if let Some(old) = self.stats_buffer.replace(chain.head_index()) {
+                    need_irq = queue
+                        .add_used(mem, old, 0)
+                        .map_err(|_| VirtioDeviceError::IoError)?;
+                }
+                Ok(need_irq)
+            }
+            _ => Err(VirtioDeviceError::Unsupported),
+        }
+    }
+
+    fn poll_queue(
+        &mut self,
+        queue_index: u16,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        if queue_index != VIRTIO_BALLOON_QUEUE_STATS || !self.stats_requested {
+            return Ok(false);
+        }
+        let Some(head) = self.stats_buffer.take() else {
+            // Keep the request pending until the driver posts a buffer.
+            return Ok(false);
+        };
+        self.stats_requested = false;
+        queue
+            .add_used(mem, head, 0)
+            .map_err(|_| VirtioDeviceError::IoError)
+    }
+
+    fn read_config(&self, offset: u64, data: &mut [u8]) {
+        let cfg = self.config_bytes();
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = usize::try_from(offset)
+                .ok()
+                .and_then(|off| off.checked_add(i))
+                .and_then(|off| cfg.get(off))
+                .copied()
+                .unwrap_or(0);
+        }
+    }
+
+    fn write_config(&mut self, offset: u64, data: &[u8]) {
+        // Only `actual` (bytes 4..8) is driver-writable.
+        let mut actual = self.actual.to_le_bytes();
+        for (i, &byte) in data.iter().enumerate() {
+            if let Some(off @ 4..=7) = offset.checked_add(i as u64) {
+                actual[(off - 4) as usize] = byte;
+            }
+        }
+        self.actual = u32::from_le_bytes(actual);
+    }
+
+    fn reset(&mut self) {
+        // `num_pages` is host policy and survives device resets (e.g. guest reboots).
+        self.features = 0;
+        self.actual = 0;
+        self.events.clear();
+        self.stats = None;
+        self.stats_buffer = None;
+        self.stats_requested = false;
+    }
+
+    fn snapshot_device_state(&self) -> Option<Vec<u8>> {
+        // - byte0: version
+        // - bytes1..5: num_pages
+        // - bytes5..9: actual
+        //
+        // The held stats buffer is runtime-only; platforms rewind the stats queue on restore so
+        // the transport re-pops it.
+        let mut out = vec![1];
+        out.extend_from_slice(&self.num_pages.to_le_bytes());
+        out.extend_from_slice(&self.actual.to_le_bytes());
+        Some(out)
+    }
+
+    fn restore_device_state(&mut self, bytes: &[u8]) {
+        if bytes.len() < 9 || bytes[0] != 1 {
+            return;
+        }
+        self.num_pages = u32::from_le_bytes(bytes[1..5].try_into().unwrap());
+        self.actual = u32::from_le_bytes(bytes[5..9].try_into().unwrap());
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
@@ -3,15 +3,19 @@ use crate::memory::write_u8;
 use crate::memory::GuestMemory;
 use crate::pci::{VIRTIO_F_RING_INDIRECT_DESC, VIRTIO_F_VERSION_1};
 use crate::queue::{DescriptorChain, VirtQueue};
-use aero_storage::{DiskError, VirtualDisk, SECTOR_SIZE};
+use aero_storage::{
+    DiskError, StorageFaultInjector, StorageRequest, StorageRequestKind, VirtualDisk, SECTOR_SIZE,
+};
 
 pub const VIRTIO_DEVICE_TYPE_BLK: u16 = 2;
 
 pub const VIRTIO_BLK_SECTOR_SIZE: u64 = SECTOR_SIZE as u64;
 
+pub const VIRTIO_BLK_F_SIZE_MAX: u64 = 1 << 1;
 pub const VIRTIO_BLK_F_SEG_MAX: u64 = 1 << 2;
 pub const VIRTIO_BLK_F_BLK_SIZE: u64 = 1 << 6;
 pub const VIRTIO_BLK_F_FLUSH: u64 = 1 << 9;
+pub const VIRTIO_BLK_F_MQ: u64 = 1 << 12;
 pub const VIRTIO_BLK_F_DISCARD: u64 = 1 << 13;
 pub const VIRTIO_BLK_F_WRITE_ZEROES: u64 = 1 << 14;
 
@@ -43,6 +47,39 @@ pub const VIRTIO_BLK_MAX_REQUEST_DATA_BYTES: u64 = 4 * 1024 * 1024;
 pub const VIRTIO_BLK_MAX_REQUEST_SECTORS: u64 =
     VIRTIO_BLK_MAX_REQUEST_DATA_BYTES / VIRTIO_BLK_SECTOR_SIZE;
 
+/// Size of every request virtqueue.
+pub const VIRTIO_BLK_QUEUE_MAX_SIZE: u16 = 128;
+
+/// Maximum number of request virtqueues (see [`VirtioBlkOptions::num_queues`]).
+///
+/// Bounded by the MSI-X table (one vector per queue plus the config vector) that fits in BAR0.
+pub const VIRTIO_BLK_MAX_QUEUES: u16 = 16;
+
+/// Optional virtio-blk features beyond the Windows 7 contract (`docs/windows7-virtio-driver-contract.md`).
+///
+/// The default is the contract v1 device: one request queue, `size_max = 0` and `seg_max` equal to
+/// the queue size minus the header and status descriptors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtioBlkOptions {
+    /// Number of request virtqueues, clamped to `1..=`[`VIRTIO_BLK_MAX_QUEUES`].
+    /// [`VIRTIO_BLK_F_MQ`] is offered when this is greater than 1.
+    pub num_queues: u16,
+    /// Advertise the per-request limits the device actually enforces instead of the contract v1
+    /// values: `seg_max` covers [`VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS`] and `size_max`
+    /// ([`VIRTIO_BLK_F_SIZE_MAX`]) is [`VIRTIO_BLK_MAX_REQUEST_DATA_BYTES`]. Together with indirect
+    /// descriptors this lets drivers submit up to 4MiB per request.
+    pub backend_limits: bool,
+}
+
+impl Default for VirtioBlkOptions {
+    fn default() -> Self {
+        Self {
+            num_queues: 1,
+            backend_limits: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VirtioBlkConfig {
     /// Capacity in 512-byte sectors.
@@ -50,12 +87,14 @@ pub struct VirtioBlkConfig {
     pub size_max: u32,
     pub seg_max: u32,
     pub blk_size: u32,
+    /// Number of request virtqueues (only meaningful when [`VIRTIO_BLK_F_MQ`] is offered).
+    pub num_queues: u16,
 }
 
 impl VirtioBlkConfig {
     // Linux `struct virtio_blk_config` layout (virtio spec):
     // capacity (8) + size_max (4) + seg_max (4) + geometry (4) + blk_size (4) +
-    // topology (8) + writeback (1) + unused0 (3) +
+    // topology (8) + writeback (1) + unused0 (1) + num_queues (2) +
     // max_discard_sectors (4) + max_discard_seg (4) + discard_sector_alignment (4) +
     // max_write_zeroes_sectors (4) + max_write_zeroes_seg (4) + write_zeroes_may_unmap (1) +
     // unused1 (3)
@@ -69,6 +108,10 @@ impl VirtioBlkConfig {
         // geometry is zeroed.
         cfg[20..24].copy_from_slice(&self.blk_size.to_le_bytes());
         // topology + writeback are left as zero.
+        if self.num_queues > 1 {
+            // Contract v1 devices keep the field zero.
+            cfg[34..36].copy_from_slice(&self.num_queues.to_le_bytes());
+        }
 
         // Discard / write zeroes limits. These are safe upper bounds for our current best-effort
         // implementation; they mainly exist so in-guest drivers can enable the operations when the
@@ -182,7 +225,52 @@ pub struct VirtioBlk {
     disk: Box<dyn VirtualDisk>,
     device_id: [u8; 20],
     features: u64,
+    options: VirtioBlkOptions,
     config: VirtioBlkConfig,
+    /// Optional latency/fault injection (see [`VirtioBlk::set_fault_injector`]). Host
+    /// configuration, not guest state.
+    fault_injector: Option<StorageFaultInjector>,
+    /// Guest time as last reported by [`VirtioBlk::set_time_ns`].
+    now_ns: u64,
+    /// Requests whose used-ring entries have not been published yet, in submission order.
+    pending: Vec<PendingCompletion>,
+    /// FLUSH requests of the current queue pass. They share a single backend flush, issued by the
+    /// next [`VirtioDevice::poll_queue`] before any of them completes.
+    held_flushes: Vec<HeldFlush>,
+    /// A write following a held FLUSH must be ordered behind it with a write barrier.
+    barrier_needed: bool,
+}
+
+/// A FLUSH request waiting for the batched backend flush covering it.
+#[derive(Debug, Clone, Copy)]
+struct HeldFlush {
+    queue_index: u16,
+    head_index: u16,
+    status_addr: u64,
+    delay_ns: u64,
+}
+
+/// A processed request waiting for [`VirtioDevice::poll_queue`] to publish its used-ring entry.
+#[derive(Debug, Clone, Copy)]
+struct PendingCompletion {
+    queue_index: u16,
+    head_index: u16,
+    due_ns: u64,
+}
+
+fn storage_request(typ: u32, sector: u64, data_len: u64) -> Option<StorageRequest> {
+    let sectors = data_len / VIRTIO_BLK_SECTOR_SIZE;
+    let (kind, lba, sectors) = match typ {
+        VIRTIO_BLK_T_IN => (StorageRequestKind::Read, sector, sectors),
+        VIRTIO_BLK_T_OUT => (StorageRequestKind::Write, sector, sectors),
+        VIRTIO_BLK_T_FLUSH => (StorageRequestKind::Flush, 0, 0),
+        // Segment tables live in guest memory; treat them as whole-device writes.
+        VIRTIO_BLK_T_DISCARD | VIRTIO_BLK_T_WRITE_ZEROES => {
+            (StorageRequestKind::Write, 0, u64::MAX)
+        }
+        _ => return None,
+    };
+    Some(StorageRequest { kind, lba, sectors })
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -263,450 +351,523 @@ fn parse_discard_write_zeroes_segments(
 
 impl VirtioBlk {
     pub fn new(disk: Box<dyn VirtualDisk>) -> Self {
-        let queue_max_size = 128u16;
+        Self::with_options(disk, VirtioBlkOptions::default())
+    }
+
+    pub fn with_options(disk: Box<dyn VirtualDisk>, options: VirtioBlkOptions) -> Self {
+        let options = VirtioBlkOptions {
+            num_queues: options.num_queues.clamp(1, VIRTIO_BLK_MAX_QUEUES),
+            ..options
+        };
+        let (size_max, seg_max) = if options.backend_limits {
+            (
+                VIRTIO_BLK_MAX_REQUEST_DATA_BYTES as u32,
+                (VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS - 2) as u32,
+            )
+        } else {
+            // Contract v1: `size_max` is unused and MUST be 0.
+            (0, u32::from(VIRTIO_BLK_QUEUE_MAX_SIZE.saturating_sub(2)))
+        };
         let config = VirtioBlkConfig {
             capacity: disk.capacity_bytes() / VIRTIO_BLK_SECTOR_SIZE,
-            // Contract v1: `size_max` is unused and MUST be 0.
-            size_max: 0,
-            seg_max: u32::from(queue_max_size.saturating_sub(2)),
+            size_max,
+            seg_max,
             // Virtio requests are still in 512-byte sectors.
             blk_size: VIRTIO_BLK_SECTOR_SIZE as u32,
+            num_queues: options.num_queues,
         };
         Self {
             disk,
             device_id: DEFAULT_DEVICE_ID,
             features: 0,
+            options,
             config,
+            fault_injector: None,
+            now_ns: 0,
+            pending: Vec::new(),
+            held_flushes: Vec::new(),
+            barrier_needed: false,
         }
     }
 
-    pub fn disk_mut(&mut self) -> &mut dyn VirtualDisk {
-        &mut *self.disk
+    pub fn options(&self) -> VirtioBlkOptions {
+        self.options
     }
 
-    pub fn device_id(&self) -> [u8; 20] {
-        self.device_id
+    /// Attach (or detach with `None`) a latency/fault injector consulted before each disk request.
+    ///
+    /// Delayed requests still execute immediately, but their used-ring entries are only published
+    /// by [`VirtioDevice::poll_queue`] once [`VirtioBlk::set_time_ns`] has advanced past the delay,
+    /// so requests may complete out of order. Pending completions are part of the device snapshot;
+    /// the injector itself is not, so a restored device without one releases them all on the next
+    /// poll, as does detaching the injector.
+    pub fn set_fault_injector(&mut self, injector: Option<StorageFaultInjector>) {
+        self.fault_injector = injector;
     }
-}
 
-impl VirtioDevice for VirtioBlk {
-    fn device_type(&self) -> u16 {
-        VIRTIO_DEVICE_TYPE_BLK
+    pub fn fault_injector(&self) -> Option<&StorageFaultInjector> {
+        self.fault_injector.as_ref()
     }
 
-    fn device_features(&self) -> u64 {
-        VIRTIO_F_VERSION_1
-            | VIRTIO_F_RING_INDIRECT_DESC
-            | VIRTIO_BLK_F_SEG_MAX
-            | VIRTIO_BLK_F_BLK_SIZE
-            | VIRTIO_BLK_F_FLUSH
-            | VIRTIO_BLK_F_DISCARD
-            | VIRTIO_BLK_F_WRITE_ZEROES
+    /// Update the guest time used to schedule injected delays.
+    pub fn set_time_ns(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
     }
 
-    fn set_features(&mut self, features: u64) {
-        self.features = features;
+    fn complete(&mut self, queue_index: u16, head_index: u16, delay_ns: u64) {
+        self.pending.push(PendingCompletion {
+            queue_index,
+            head_index,
+            due_ns: self.now_ns.saturating_add(delay_ns),
+        });
     }
 
-    fn num_queues(&self) -> u16 {
-        1
+    /// Issue the backend flush covering every held FLUSH request and queue their completions.
+    fn commit_held_flushes(&mut self, mem: &mut dyn GuestMemory) {
+        if self.held_flushes.is_empty() {
+            return;
+        }
+        let status = match self.disk.flush() {
+            Ok(()) => VIRTIO_BLK_S_OK,
+            Err(_) => VIRTIO_BLK_S_IOERR,
+        };
+        self.barrier_needed = false;
+        for held in std::mem::take(&mut self.held_flushes) {
+            let _ = write_u8(mem, held.status_addr, status);
+            self.complete(held.queue_index, held.head_index, held.delay_ns);
+        }
     }
 
-    fn queue_max_size(&self, _queue: u16) -> u16 {
-        128
+    pub fn disk_mut(&mut self) -> &mut dyn VirtualDisk {
+        &mut *self.disk
     }
 
-    fn process_queue(
+    pub fn device_id(&self) -> [u8; 20] {
+        self.device_id
+    }
+
+    /// Execute a parsed request against the backend and return its status byte.
+    fn execute_request(
         &mut self,
-        queue_index: u16,
-        chain: DescriptorChain,
-        queue: &mut VirtQueue,
+        typ: u32,
+        sector: u64,
+        data_segs: Vec<(crate::queue::Descriptor, usize, usize)>,
+        total_data_len: u64,
         mem: &mut dyn GuestMemory,
-    ) -> Result<bool, VirtioDeviceError> {
-        if queue_index != 0 {
-            return Err(VirtioDeviceError::Unsupported);
-        }
-
-        let descs = chain.descriptors();
-        if descs.is_empty() {
-            return Ok(false);
-        }
-
-        let status_desc = descs[descs.len() - 1];
-        let can_write_status = status_desc.is_write_only() && status_desc.len != 0;
-
-        // DoS guard: avoid unbounded per-request work/allocations on pathological descriptor chains
-        // (especially when indirect descriptors are enabled).
-        if descs.len() > VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS {
-            let status = VIRTIO_BLK_S_IOERR;
-            if can_write_status {
-                // Best-effort: if the status buffer is invalid/out-of-bounds, still advance the
-                // used ring so the guest can reclaim the descriptor chain.
-                let _ = write_u8(mem, status_desc.addr, status);
-            }
-            return queue
-                // Contract v1: virtio-blk drivers must not depend on used lengths.
-                .add_used(mem, chain.head_index(), 0)
-                .map_err(|_| VirtioDeviceError::IoError);
-        }
-
-        // If the status descriptor is invalid, treat the whole request as invalid. We still
-        // advance the used ring so the guest can reclaim the descriptors, but we avoid touching
-        // any other guest buffers or backend state.
-        if !can_write_status {
-            return queue
-                .add_used(mem, chain.head_index(), 0)
-                .map_err(|_| VirtioDeviceError::IoError);
-        }
-
-        // Read the 16-byte request header.
-        let mut hdr = [0u8; 16];
-        let mut hdr_written = 0usize;
-        let mut d_idx = 0usize;
-        let mut d_off = 0usize;
-        let mut header_ok = true;
-        while hdr_written < hdr.len() {
-            if d_idx >= descs.len().saturating_sub(1) {
-                header_ok = false;
-                break;
-            }
-            let d = descs[d_idx];
-            if d.is_write_only() {
-                header_ok = false;
-                break;
-            }
-            let avail = (d.len as usize).saturating_sub(d_off);
-            if avail == 0 {
-                d_idx += 1;
-                d_off = 0;
-                continue;
-            }
-            let take = avail.min(hdr.len() - hdr_written);
-            let Some(addr) = d.addr.checked_add(d_off as u64) else {
-                header_ok = false;
-                break;
-            };
-            let Ok(()) = mem.read(addr, &mut hdr[hdr_written..hdr_written + take]) else {
-                header_ok = false;
-                break;
-            };
-            hdr_written += take;
-            d_off += take;
-            if d_off == d.len as usize {
-                d_idx += 1;
-                d_off = 0;
-            }
-        }
-
-        if !header_ok {
-            // Header is malformed (short, out-of-bounds, or wrong direction). Fail the request
-            // without scanning the remaining descriptors so malformed chains can't force extra
-            // work beyond the header read.
-            if can_write_status {
-                let _ = write_u8(mem, status_desc.addr, VIRTIO_BLK_S_IOERR);
-            }
-            return queue
-                // Contract v1: virtio-blk drivers must not depend on used lengths.
-                .add_used(mem, chain.head_index(), 0)
-                .map_err(|_| VirtioDeviceError::IoError);
-        }
-
-        // Build data segments (everything between header cursor and status descriptor), enforcing
-        // a per-request payload cap to avoid unbounded allocations or work.
-        let mut data_segs: Vec<(crate::queue::Descriptor, usize, usize)> = Vec::new();
-        let mut total_data_len: u64 = 0;
-        let mut data_len_ok = true;
-        let data_end_idx = descs.len().saturating_sub(1);
-        let mut seg_idx = d_idx;
-        let mut seg_off = d_off;
-        while seg_idx < data_end_idx {
-            let d = descs[seg_idx];
-            let d_len = d.len as usize;
-            if seg_off > d_len {
-                data_len_ok = false;
-                break;
-            }
-            let seg_len = d_len - seg_off;
-            if seg_len != 0 {
-                let seg_len_u64 = u64::try_from(seg_len).unwrap_or(u64::MAX);
-                total_data_len = match total_data_len.checked_add(seg_len_u64) {
-                    Some(v) => v,
-                    None => {
-                        data_len_ok = false;
-                        break;
-                    }
-                };
-                if total_data_len > VIRTIO_BLK_MAX_REQUEST_DATA_BYTES {
-                    data_len_ok = false;
-                    break;
-                }
-                data_segs.push((d, seg_off, seg_len));
-            }
-            seg_idx += 1;
-            seg_off = 0;
-        }
-
-        let mut status = VIRTIO_BLK_S_IOERR;
-        if data_len_ok {
-            status = VIRTIO_BLK_S_OK;
-
-            let typ = u32::from_le_bytes(hdr[0..4].try_into().unwrap());
-            let sector = u64::from_le_bytes(hdr[8..16].try_into().unwrap());
-
-            match typ {
-                VIRTIO_BLK_T_IN => {
-                    if data_segs.is_empty()
-                        || !total_data_len.is_multiple_of(VIRTIO_BLK_SECTOR_SIZE)
-                    {
-                        status = VIRTIO_BLK_S_IOERR;
-                    } else if let Some(sector_off) = sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE) {
-                        if let Some(end_off) = sector_off.checked_add(total_data_len) {
-                            if end_off > self.disk.capacity_bytes() {
-                                status = VIRTIO_BLK_S_IOERR;
-                            } else {
-                                let mut offset = sector_off;
-                                // Chunked I/O buffer so we don't need to borrow a `&mut [u8]`
-                                // directly into guest memory.
-                                let mut scratch = vec![0u8; 64 * 1024];
-                                for (d, seg_off, seg_len) in &data_segs {
-                                    if !d.is_write_only() {
+    ) -> u8 {
+        let mut status = VIRTIO_BLK_S_OK;
+
+        match typ {
+            VIRTIO_BLK_T_IN => {
+                if data_segs.is_empty() || !total_data_len.is_multiple_of(VIRTIO_BLK_SECTOR_SIZE) {
+                    status = VIRTIO_BLK_S_IOERR;
+                } else if let Some(sector_off) = sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE) {
+                    if let Some(end_off) = sector_off.checked_add(total_data_len) {
+                        if end_off > self.disk.capacity_bytes() {
+                            status = VIRTIO_BLK_S_IOERR;
+                        } else {
+                            let mut offset = sector_off;
+                            // Chunked I/O buffer so we don't need to borrow a `&mut [u8]`
+                            // directly into guest memory.
+                            let mut scratch = vec![0u8; 64 * 1024];
+                            for (d, seg_off, seg_len) in &data_segs {
+                                if !d.is_write_only() {
+                                    status = VIRTIO_BLK_S_IOERR;
+                                    break;
+                                }
+                                let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
+                                    status = VIRTIO_BLK_S_IOERR;
+                                    break;
+                                };
+                                let mut remaining = *seg_len;
+                                let mut cur_addr = addr;
+                                while remaining != 0 {
+                                    let take = remaining.min(scratch.len());
+                                    if self.disk.read_at(offset, &mut scratch[..take]).is_err()
+                                        || mem.write(cur_addr, &scratch[..take]).is_err()
+                                    {
                                         status = VIRTIO_BLK_S_IOERR;
                                         break;
                                     }
-                                    let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
-                                        status = VIRTIO_BLK_S_IOERR;
-                                        break;
+                                    offset = match offset.checked_add(take as u64) {
+                                        Some(v) => v,
+                                        None => {
+                                            status = VIRTIO_BLK_S_IOERR;
+                                            break;
+                                        }
                                     };
-                                    let mut remaining = *seg_len;
-                                    let mut cur_addr = addr;
-                                    while remaining != 0 {
-                                        let take = remaining.min(scratch.len());
-                                        if self.disk.read_at(offset, &mut scratch[..take]).is_err()
-                                            || mem.write(cur_addr, &scratch[..take]).is_err()
-                                        {
+                                    cur_addr = match cur_addr.checked_add(take as u64) {
+                                        Some(v) => v,
+                                        None => {
                                             status = VIRTIO_BLK_S_IOERR;
                                             break;
                                         }
-                                        offset = match offset.checked_add(take as u64) {
-                                            Some(v) => v,
-                                            None => {
-                                                status = VIRTIO_BLK_S_IOERR;
-                                                break;
-                                            }
-                                        };
-                                        cur_addr = match cur_addr.checked_add(take as u64) {
-                                            Some(v) => v,
-                                            None => {
-                                                status = VIRTIO_BLK_S_IOERR;
-                                                break;
-                                            }
-                                        };
-                                        remaining = remaining.saturating_sub(take);
-                                    }
-                                    if status != VIRTIO_BLK_S_OK {
-                                        break;
-                                    }
+                                    };
+                                    remaining = remaining.saturating_sub(take);
+                                }
+                                if status != VIRTIO_BLK_S_OK {
+                                    break;
                                 }
                             }
-                        } else {
-                            status = VIRTIO_BLK_S_IOERR;
                         }
                     } else {
                         status = VIRTIO_BLK_S_IOERR;
                     }
+                } else {
+                    status = VIRTIO_BLK_S_IOERR;
                 }
-                VIRTIO_BLK_T_OUT => {
-                    if data_segs.is_empty()
-                        || !total_data_len.is_multiple_of(VIRTIO_BLK_SECTOR_SIZE)
-                    {
-                        status = VIRTIO_BLK_S_IOERR;
-                    } else if let Some(sector_off) = sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE) {
-                        if let Some(end_off) = sector_off.checked_add(total_data_len) {
-                            if end_off > self.disk.capacity_bytes() {
-                                status = VIRTIO_BLK_S_IOERR;
-                            } else {
-                                let mut offset = sector_off;
-                                let mut scratch = vec![0u8; 64 * 1024];
-                                for (d, seg_off, seg_len) in &data_segs {
-                                    if d.is_write_only() {
+            }
+            VIRTIO_BLK_T_OUT => {
+                if data_segs.is_empty() || !total_data_len.is_multiple_of(VIRTIO_BLK_SECTOR_SIZE) {
+                    status = VIRTIO_BLK_S_IOERR;
+                } else if let Some(sector_off) = sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE) {
+                    if let Some(end_off) = sector_off.checked_add(total_data_len) {
+                        if end_off > self.disk.capacity_bytes() {
+                            status = VIRTIO_BLK_S_IOERR;
+                        } else {
+                            let mut offset = sector_off;
+                            let mut scratch = vec![0u8; 64 * 1024];
+                            for (d, seg_off, seg_len) in &data_segs {
+                                if d.is_write_only() {
+                                    status = VIRTIO_BLK_S_IOERR;
+                                    break;
+                                }
+                                let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
+                                    status = VIRTIO_BLK_S_IOERR;
+                                    break;
+                                };
+                                let mut remaining = *seg_len;
+                                let mut cur_addr = addr;
+                                while remaining != 0 {
+                                    let take = remaining.min(scratch.len());
+                                    if mem.read(cur_addr, &mut scratch[..take]).is_err()
+                                        || self.disk.write_at(offset, &scratch[..take]).is_err()
+                                    {
                                         status = VIRTIO_BLK_S_IOERR;
                                         break;
                                     }
-                                    let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
-                                        status = VIRTIO_BLK_S_IOERR;
-                                        break;
+                                    offset = match offset.checked_add(take as u64) {
+                                        Some(v) => v,
+                                        None => {
+                                            status = VIRTIO_BLK_S_IOERR;
+                                            break;
+                                        }
                                     };
-                                    let mut remaining = *seg_len;
-                                    let mut cur_addr = addr;
-                                    while remaining != 0 {
-                                        let take = remaining.min(scratch.len());
-                                        if mem.read(cur_addr, &mut scratch[..take]).is_err()
-                                            || self.disk.write_at(offset, &scratch[..take]).is_err()
-                                        {
+                                    cur_addr = match cur_addr.checked_add(take as u64) {
+                                        Some(v) => v,
+                                        None => {
                                             status = VIRTIO_BLK_S_IOERR;
                                             break;
                                         }
-                                        offset = match offset.checked_add(take as u64) {
-                                            Some(v) => v,
-                                            None => {
-                                                status = VIRTIO_BLK_S_IOERR;
-                                                break;
-                                            }
-                                        };
-                                        cur_addr = match cur_addr.checked_add(take as u64) {
-                                            Some(v) => v,
-                                            None => {
-                                                status = VIRTIO_BLK_S_IOERR;
-                                                break;
-                                            }
-                                        };
-                                        remaining = remaining.saturating_sub(take);
-                                    }
-                                    if status != VIRTIO_BLK_S_OK {
-                                        break;
-                                    }
+                                    };
+                                    remaining = remaining.saturating_sub(take);
+                                }
+                                if status != VIRTIO_BLK_S_OK {
+                                    break;
                                 }
                             }
-                        } else {
-                            status = VIRTIO_BLK_S_IOERR;
                         }
                     } else {
                         status = VIRTIO_BLK_S_IOERR;
                     }
+                } else {
+                    status = VIRTIO_BLK_S_IOERR;
                 }
-                VIRTIO_BLK_T_FLUSH => {
-                    if (self.features & VIRTIO_BLK_F_FLUSH) == 0 {
-                        status = VIRTIO_BLK_S_UNSUPP;
-                    } else if self.disk.flush().is_err() {
-                        status = VIRTIO_BLK_S_IOERR;
-                    }
+            }
+            VIRTIO_BLK_T_FLUSH => {
+                if (self.features & VIRTIO_BLK_F_FLUSH) == 0 {
+                    status = VIRTIO_BLK_S_UNSUPP;
+                } else if self.disk.flush().is_err() {
+                    status = VIRTIO_BLK_S_IOERR;
                 }
-                VIRTIO_BLK_T_GET_ID => {
-                    // The driver supplies a data buffer (write-only) and expects up to 20 bytes
-                    // back. If the buffer is smaller, we write as much as fits; if larger, we
-                    // truncate and still succeed.
-                    if data_segs.is_empty() {
-                        status = VIRTIO_BLK_S_IOERR;
-                    } else {
-                        let mut remaining: &[u8] = &self.device_id;
-                        for (d, seg_off, seg_len) in &data_segs {
-                            if remaining.is_empty() {
+            }
+            VIRTIO_BLK_T_GET_ID => {
+                // The driver supplies a data buffer (write-only) and expects up to 20 bytes
+                // back. If the buffer is smaller, we write as much as fits; if larger, we
+                // truncate and still succeed.
+                if data_segs.is_empty() {
+                    status = VIRTIO_BLK_S_IOERR;
+                } else {
+                    let mut remaining: &[u8] = &self.device_id;
+                    for (d, seg_off, seg_len) in &data_segs {
+                        if remaining.is_empty() {
+                            break;
+                        }
+                        if !d.is_write_only() {
+                            status = VIRTIO_BLK_S_IOERR;
+                            break;
+                        }
+                        let write_len = (*seg_len).min(remaining.len());
+                        if write_len == 0 {
+                            continue;
+                        }
+                        let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
+                            status = VIRTIO_BLK_S_IOERR;
+                            break;
+                        };
+                        if mem.write(addr, &remaining[..write_len]).is_err() {
+                            status = VIRTIO_BLK_S_IOERR;
+                            break;
+                        };
+                        remaining = &remaining[write_len..];
+                    }
+                }
+            }
+            VIRTIO_BLK_T_DISCARD => {
+                if (self.features & VIRTIO_BLK_F_DISCARD) == 0 {
+                    status = VIRTIO_BLK_S_UNSUPP;
+                } else {
+                    let segs = match parse_discard_write_zeroes_segments(
+                        mem,
+                        &data_segs,
+                        self.config.seg_max,
+                    ) {
+                        Ok(v) => v,
+                        Err(_) => {
+                            status = VIRTIO_BLK_S_IOERR;
+                            Vec::new()
+                        }
+                    };
+
+                    if status == VIRTIO_BLK_S_OK {
+                        let blk_size = u64::from(self.config.blk_size).max(VIRTIO_BLK_SECTOR_SIZE);
+                        let align_sectors = (blk_size / VIRTIO_BLK_SECTOR_SIZE).max(1);
+                        let disk_len = self.disk.capacity_bytes();
+
+                        // Validate all segments up-front so a rejected request cannot partially
+                        // modify backend state.
+                        let mut validated: Vec<(u64, u64)> = Vec::with_capacity(segs.len());
+                        let mut total_sectors: u64 = 0;
+                        for seg in &segs {
+                            let num_sectors = u64::from(seg.num_sectors);
+                            total_sectors = match total_sectors.checked_add(num_sectors) {
+                                Some(v) => v,
+                                None => {
+                                    status = VIRTIO_BLK_S_IOERR;
+                                    break;
+                                }
+                            };
+                            if total_sectors > VIRTIO_BLK_MAX_REQUEST_SECTORS {
+                                status = VIRTIO_BLK_S_IOERR;
                                 break;
                             }
-                            if !d.is_write_only() {
+                            if seg.sector % align_sectors != 0 || num_sectors % align_sectors != 0 {
                                 status = VIRTIO_BLK_S_IOERR;
                                 break;
                             }
-                            let write_len = (*seg_len).min(remaining.len());
-                            if write_len == 0 {
-                                continue;
-                            }
-                            let Some(addr) = d.addr.checked_add(*seg_off as u64) else {
+
+                            let Some(byte_off) = seg.sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
+                            else {
                                 status = VIRTIO_BLK_S_IOERR;
                                 break;
                             };
-                            if mem.write(addr, &remaining[..write_len]).is_err() {
+                            let Some(byte_len) = num_sectors.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
+                            else {
                                 status = VIRTIO_BLK_S_IOERR;
                                 break;
                             };
-                            remaining = &remaining[write_len..];
-                        }
-                    }
-                }
-                VIRTIO_BLK_T_DISCARD => {
-                    if (self.features & VIRTIO_BLK_F_DISCARD) == 0 {
-                        status = VIRTIO_BLK_S_UNSUPP;
-                    } else {
-                        let segs = match parse_discard_write_zeroes_segments(
-                            mem,
-                            &data_segs,
-                            self.config.seg_max,
-                        ) {
-                            Ok(v) => v,
-                            Err(_) => {
+                            let Some(end_off) = byte_off.checked_add(byte_len) else {
                                 status = VIRTIO_BLK_S_IOERR;
-                                Vec::new()
+                                break;
+                            };
+                            if end_off > disk_len {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
                             }
-                        };
 
+                            validated.push((byte_off, byte_len));
+                        }
+                        // Best-effort: discard is advisory. Prefer disk hole-punching, but
+                        // emulate by writing zeros when the disk doesn't reclaim (common for raw
+                        // disks).
                         if status == VIRTIO_BLK_S_OK {
-                            let blk_size =
-                                u64::from(self.config.blk_size).max(VIRTIO_BLK_SECTOR_SIZE);
-                            let align_sectors = (blk_size / VIRTIO_BLK_SECTOR_SIZE).max(1);
-                            let disk_len = self.disk.capacity_bytes();
-
-                            // Validate all segments up-front so a rejected request cannot partially
-                            // modify backend state.
-                            let mut validated: Vec<(u64, u64)> = Vec::with_capacity(segs.len());
-                            let mut total_sectors: u64 = 0;
-                            for seg in &segs {
-                                let num_sectors = u64::from(seg.num_sectors);
-                                total_sectors = match total_sectors.checked_add(num_sectors) {
-                                    Some(v) => v,
-                                    None => {
-                                        status = VIRTIO_BLK_S_IOERR;
-                                        break;
+                            // Buffer used for chunked zero writes. Use a block-size-aligned
+                            // chunk so backends that care about write alignment are not
+                            // penalized.
+                            let blk_usize = usize::try_from(blk_size).unwrap_or(SECTOR_SIZE);
+                            let max_chunk = 64 * 1024usize;
+                            let mut chunk_size = if blk_usize > max_chunk {
+                                blk_usize
+                            } else {
+                                (max_chunk / blk_usize).saturating_mul(blk_usize)
+                            };
+                            chunk_size = chunk_size.max(blk_usize).max(SECTOR_SIZE);
+                            let zero_buf = vec![0u8; chunk_size];
+                            let mut read_buf = vec![0u8; chunk_size];
+
+                            for (byte_off, byte_len) in validated {
+                                if byte_len == 0 {
+                                    continue;
+                                }
+
+                                let discard_result = self.disk.discard_range(byte_off, byte_len);
+                                let mut needs_zero_fallback = discard_result.is_err();
+
+                                if !needs_zero_fallback {
+                                    // If `discard_range` was a no-op, ensure guest-visible
+                                    // semantics by scanning the discarded range and writing
+                                    // zeros only for chunks that still contain non-zero bytes.
+                                    //
+                                    // This preserves hole-punching on sparse backends: if a
+                                    // chunk already reads as zero (e.g. fully deallocated
+                                    // sparse block), we skip the explicit zero write and avoid
+                                    // re-allocating the block.
+                                    let mut scan_off = byte_off;
+                                    let mut scan_remaining = byte_len;
+                                    while scan_remaining != 0 {
+                                        let take =
+                                            scan_remaining.min(read_buf.len() as u64) as usize;
+                                        match self.disk.read_at(scan_off, &mut read_buf[..take]) {
+                                            Ok(()) => {
+                                                if read_buf[..take].iter().any(|b| *b != 0)
+                                                    && self
+                                                        .disk
+                                                        .write_at(scan_off, &zero_buf[..take])
+                                                        .is_err()
+                                                {
+                                                    status = VIRTIO_BLK_S_IOERR;
+                                                    break;
+                                                }
+                                            }
+                                            Err(_) => {
+                                                needs_zero_fallback = true;
+                                                break;
+                                            }
+                                        }
+                                        scan_off = match scan_off.checked_add(take as u64) {
+                                            Some(v) => v,
+                                            None => {
+                                                needs_zero_fallback = true;
+                                                break;
+                                            }
+                                        };
+                                        scan_remaining = scan_remaining.saturating_sub(take as u64);
                                     }
-                                };
-                                if total_sectors > VIRTIO_BLK_MAX_REQUEST_SECTORS {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
                                 }
-                                if seg.sector % align_sectors != 0
-                                    || num_sectors % align_sectors != 0
-                                {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
+
+                                if needs_zero_fallback && status == VIRTIO_BLK_S_OK {
+                                    let mut remaining = byte_len;
+                                    let mut cur_off = byte_off;
+                                    while remaining != 0 {
+                                        let take = remaining.min(zero_buf.len() as u64) as usize;
+                                        if self.disk.write_at(cur_off, &zero_buf[..take]).is_err() {
+                                            status = VIRTIO_BLK_S_IOERR;
+                                            break;
+                                        }
+                                        cur_off = match cur_off.checked_add(take as u64) {
+                                            Some(v) => v,
+                                            None => {
+                                                status = VIRTIO_BLK_S_IOERR;
+                                                break;
+                                            }
+                                        };
+                                        remaining = remaining.saturating_sub(take as u64);
+                                    }
                                 }
 
-                                let Some(byte_off) = seg.sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
-                                else {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                };
-                                let Some(byte_len) =
-                                    num_sectors.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
-                                else {
-                                    status = VIRTIO_BLK_S_IOERR;
+                                if status != VIRTIO_BLK_S_OK {
                                     break;
-                                };
-                                let Some(end_off) = byte_off.checked_add(byte_len) else {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                };
-                                if end_off > disk_len {
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            VIRTIO_BLK_T_WRITE_ZEROES => {
+                if (self.features & VIRTIO_BLK_F_WRITE_ZEROES) == 0 {
+                    status = VIRTIO_BLK_S_UNSUPP;
+                } else {
+                    let segs = match parse_discard_write_zeroes_segments(
+                        mem,
+                        &data_segs,
+                        self.config.seg_max,
+                    ) {
+                        Ok(v) => v,
+                        Err(_) => {
+                            status = VIRTIO_BLK_S_IOERR;
+                            Vec::new()
+                        }
+                    };
+
+                    if status == VIRTIO_BLK_S_OK {
+                        let blk_size = u64::from(self.config.blk_size).max(VIRTIO_BLK_SECTOR_SIZE);
+                        let align_sectors = (blk_size / VIRTIO_BLK_SECTOR_SIZE).max(1);
+                        let disk_len = self.disk.capacity_bytes();
+
+                        // Validate all segments up-front so a rejected request cannot partially
+                        // modify disk state.
+                        let mut validated: Vec<(u64, u64, u32)> = Vec::with_capacity(segs.len());
+                        let mut total_sectors: u64 = 0;
+                        for seg in &segs {
+                            let num_sectors = u64::from(seg.num_sectors);
+                            total_sectors = match total_sectors.checked_add(num_sectors) {
+                                Some(v) => v,
+                                None => {
                                     status = VIRTIO_BLK_S_IOERR;
                                     break;
                                 }
-
-                                validated.push((byte_off, byte_len));
+                            };
+                            if total_sectors > VIRTIO_BLK_MAX_REQUEST_SECTORS {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
                             }
-                            // Best-effort: discard is advisory. Prefer disk hole-punching, but
-                            // emulate by writing zeros when the disk doesn't reclaim (common for raw
-                            // disks).
-                            if status == VIRTIO_BLK_S_OK {
-                                // Buffer used for chunked zero writes. Use a block-size-aligned
-                                // chunk so backends that care about write alignment are not
-                                // penalized.
-                                let blk_usize = usize::try_from(blk_size).unwrap_or(SECTOR_SIZE);
-                                let max_chunk = 64 * 1024usize;
-                                let mut chunk_size = if blk_usize > max_chunk {
-                                    blk_usize
-                                } else {
-                                    (max_chunk / blk_usize).saturating_mul(blk_usize)
-                                };
-                                chunk_size = chunk_size.max(blk_usize).max(SECTOR_SIZE);
-                                let zero_buf = vec![0u8; chunk_size];
-                                let mut read_buf = vec![0u8; chunk_size];
+                            if seg.sector % align_sectors != 0 || num_sectors % align_sectors != 0 {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
+                            }
+                            let Some(byte_off) = seg.sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
+                            else {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
+                            };
+                            let Some(byte_len) = num_sectors.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
+                            else {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
+                            };
+                            let Some(end_off) = byte_off.checked_add(byte_len) else {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
+                            };
+                            if end_off > disk_len {
+                                status = VIRTIO_BLK_S_IOERR;
+                                break;
+                            }
+                            validated.push((byte_off, byte_len, seg.flags));
+                        }
 
-                                for (byte_off, byte_len) in validated {
-                                    if byte_len == 0 {
-                                        continue;
-                                    }
+                        if status == VIRTIO_BLK_S_OK {
+                            // Buffer used for chunked zero writes. Use a block-size-aligned chunk
+                            // so backends that care about write alignment are not penalized.
+                            let blk_usize = usize::try_from(blk_size).unwrap_or(SECTOR_SIZE);
+                            let max_chunk = 64 * 1024usize;
+                            let mut chunk_size = if blk_usize > max_chunk {
+                                blk_usize
+                            } else {
+                                (max_chunk / blk_usize).saturating_mul(blk_usize)
+                            };
+                            chunk_size = chunk_size.max(blk_usize).max(SECTOR_SIZE);
+                            let zero_buf = vec![0u8; chunk_size];
+                            let needs_read_buf = validated.iter().any(|(_, _, flags)| {
+                                (flags & VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP) != 0
+                            });
+                            let mut read_buf = if needs_read_buf {
+                                vec![0u8; chunk_size]
+                            } else {
+                                Vec::new()
+                            };
+
+                            'seg_loop: for (byte_off, byte_len, flags) in validated {
+                                if byte_len == 0 {
+                                    continue;
+                                }
 
-                                    let discard_result =
-                                        self.disk.discard_range(byte_off, byte_len);
-                                    let mut needs_zero_fallback = discard_result.is_err();
+                                // If the driver requests UNMAP, treat WRITE_ZEROES as a best-effort
+                                // discard (hole punch) and fall back to explicit zero writes only
+                                // if needed to enforce read-after-write semantics.
+                                if (flags & VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP) != 0 {
+                                    let mut needs_zero_fallback =
+                                        self.disk.discard_range(byte_off, byte_len).is_err();
 
                                     if !needs_zero_fallback {
                                         // If `discard_range` was a no-op, ensure guest-visible
@@ -725,14 +886,14 @@ impl VirtioDevice for VirtioBlk {
                                             match self.disk.read_at(scan_off, &mut read_buf[..take])
                                             {
                                                 Ok(()) => {
-                                                    if read_buf[..take].iter().any(|b| *b != 0)
+                                                    if read_buf[..take].iter().any(|&b| b != 0)
                                                         && self
                                                             .disk
                                                             .write_at(scan_off, &zero_buf[..take])
                                                             .is_err()
                                                     {
                                                         status = VIRTIO_BLK_S_IOERR;
-                                                        break;
+                                                        break 'seg_loop;
                                                     }
                                                 }
                                                 Err(_) => {
@@ -753,8 +914,8 @@ impl VirtioDevice for VirtioBlk {
                                     }
 
                                     if needs_zero_fallback && status == VIRTIO_BLK_S_OK {
-                                        let mut remaining = byte_len;
                                         let mut cur_off = byte_off;
+                                        let mut remaining = byte_len;
                                         while remaining != 0 {
                                             let take =
                                                 remaining.min(zero_buf.len() as u64) as usize;
@@ -764,232 +925,243 @@ impl VirtioDevice for VirtioBlk {
                                                 .is_err()
                                             {
                                                 status = VIRTIO_BLK_S_IOERR;
-                                                break;
+                                                break 'seg_loop;
                                             }
                                             cur_off = match cur_off.checked_add(take as u64) {
                                                 Some(v) => v,
                                                 None => {
                                                     status = VIRTIO_BLK_S_IOERR;
-                                                    break;
+                                                    break 'seg_loop;
                                                 }
                                             };
                                             remaining = remaining.saturating_sub(take as u64);
                                         }
                                     }
-
-                                    if status != VIRTIO_BLK_S_OK {
-                                        break;
+                                } else {
+                                    let mut cur_off = byte_off;
+                                    let mut remaining = byte_len;
+                                    while remaining != 0 {
+                                        let take = remaining.min(zero_buf.len() as u64) as usize;
+                                        if self.disk.write_at(cur_off, &zero_buf[..take]).is_err() {
+                                            status = VIRTIO_BLK_S_IOERR;
+                                            break 'seg_loop;
+                                        }
+                                        cur_off = match cur_off.checked_add(take as u64) {
+                                            Some(v) => v,
+                                            None => {
+                                                status = VIRTIO_BLK_S_IOERR;
+                                                break 'seg_loop;
+                                            }
+                                        };
+                                        remaining = remaining.saturating_sub(take as u64);
                                     }
                                 }
                             }
                         }
                     }
                 }
-                VIRTIO_BLK_T_WRITE_ZEROES => {
-                    if (self.features & VIRTIO_BLK_F_WRITE_ZEROES) == 0 {
-                        status = VIRTIO_BLK_S_UNSUPP;
-                    } else {
-                        let segs = match parse_discard_write_zeroes_segments(
-                            mem,
-                            &data_segs,
-                            self.config.seg_max,
-                        ) {
-                            Ok(v) => v,
-                            Err(_) => {
-                                status = VIRTIO_BLK_S_IOERR;
-                                Vec::new()
-                            }
-                        };
+            }
+            _ => status = VIRTIO_BLK_S_UNSUPP,
+        }
+        status
+    }
+}
 
-                        if status == VIRTIO_BLK_S_OK {
-                            let blk_size =
-                                u64::from(self.config.blk_size).max(VIRTIO_BLK_SECTOR_SIZE);
-                            let align_sectors = (blk_size / VIRTIO_BLK_SECTOR_SIZE).max(1);
-                            let disk_len = self.disk.capacity_bytes();
-
-                            // Validate all segments up-front so a rejected request cannot partially
-                            // modify disk state.
-                            let mut validated: Vec<(u64, u64, u32)> =
-                                Vec::with_capacity(segs.len());
-                            let mut total_sectors: u64 = 0;
-                            for seg in &segs {
-                                let num_sectors = u64::from(seg.num_sectors);
-                                total_sectors = match total_sectors.checked_add(num_sectors) {
-                                    Some(v) => v,
-                                    None => {
-                                        status = VIRTIO_BLK_S_IOERR;
-                                        break;
-                                    }
-                                };
-                                if total_sectors > VIRTIO_BLK_MAX_REQUEST_SECTORS {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                }
-                                if seg.sector % align_sectors != 0
-                                    || num_sectors % align_sectors != 0
-                                {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                }
-                                let Some(byte_off) = seg.sector.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
-                                else {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                };
-                                let Some(byte_len) =
-                                    num_sectors.checked_mul(VIRTIO_BLK_SECTOR_SIZE)
-                                else {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                };
-                                let Some(end_off) = byte_off.checked_add(byte_len) else {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                };
-                                if end_off > disk_len {
-                                    status = VIRTIO_BLK_S_IOERR;
-                                    break;
-                                }
-                                validated.push((byte_off, byte_len, seg.flags));
-                            }
+impl VirtioDevice for VirtioBlk {
+    fn device_type(&self) -> u16 {
+        VIRTIO_DEVICE_TYPE_BLK
+    }
 
-                            if status == VIRTIO_BLK_S_OK {
-                                // Buffer used for chunked zero writes. Use a block-size-aligned chunk
-                                // so backends that care about write alignment are not penalized.
-                                let blk_usize = usize::try_from(blk_size).unwrap_or(SECTOR_SIZE);
-                                let max_chunk = 64 * 1024usize;
-                                let mut chunk_size = if blk_usize > max_chunk {
-                                    blk_usize
-                                } else {
-                                    (max_chunk / blk_usize).saturating_mul(blk_usize)
-                                };
-                                chunk_size = chunk_size.max(blk_usize).max(SECTOR_SIZE);
-                                let zero_buf = vec![0u8; chunk_size];
-                                let needs_read_buf = validated.iter().any(|(_, _, flags)| {
-                                    (flags & VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP) != 0
-                                });
-                                let mut read_buf = if needs_read_buf {
-                                    vec![0u8; chunk_size]
-                                } else {
-                                    Vec::new()
-                                };
+    fn device_features(&self) -> u64 {
+        let mut features = VIRTIO_F_VERSION_1
+            | VIRTIO_F_RING_INDIRECT_DESC
+            | VIRTIO_BLK_F_SEG_MAX
+            | VIRTIO_BLK_F_BLK_SIZE
+            | VIRTIO_BLK_F_FLUSH
+            | VIRTIO_BLK_F_DISCARD
+            | VIRTIO_BLK_F_WRITE_ZEROES;
+        if self.options.backend_limits {
+            features |= VIRTIO_BLK_F_SIZE_MAX;
+        }
+        if self.options.num_queues > 1 {
+            features |= VIRTIO_BLK_F_MQ;
+        }
+        features
+    }
 
-                                'seg_loop: for (byte_off, byte_len, flags) in validated {
-                                    if byte_len == 0 {
-                                        continue;
-                                    }
+    fn set_features(&mut self, features: u64) {
+        self.features = features;
+    }
 
-                                    // If the driver requests UNMAP, treat WRITE_ZEROES as a best-effort
-                                    // discard (hole punch) and fall back to explicit zero writes only
-                                    // if needed to enforce read-after-write semantics.
-                                    if (flags & VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP) != 0 {
-                                        let mut needs_zero_fallback =
-                                            self.disk.discard_range(byte_off, byte_len).is_err();
-
-                                        if !needs_zero_fallback {
-                                            // If `discard_range` was a no-op, ensure guest-visible
-                                            // semantics by scanning the discarded range and writing
-                                            // zeros only for chunks that still contain non-zero bytes.
-                                            //
-                                            // This preserves hole-punching on sparse backends: if a
-                                            // chunk already reads as zero (e.g. fully deallocated
-                                            // sparse block), we skip the explicit zero write and avoid
-                                            // re-allocating the block.
-                                            let mut scan_off = byte_off;
-                                            let mut scan_remaining = byte_len;
-                                            while scan_remaining != 0 {
-                                                let take = scan_remaining.min(read_buf.len() as u64)
-                                                    as usize;
-                                                match self
-                                                    .disk
-                                                    .read_at(scan_off, &mut read_buf[..take])
-                                                {
-                                                    Ok(()) => {
-                                                        if read_buf[..take].iter().any(|&b| b != 0)
-                                                            && self
-                                                                .disk
-                                                                .write_at(
-                                                                    scan_off,
-                                                                    &zero_buf[..take],
-                                                                )
-                                                                .is_err()
-                                                        {
-                                                            status = VIRTIO_BLK_S_IOERR;
-                                                            break 'seg_loop;
-                                                        }
-                                                    }
-                                                    Err(_) => {
-                                                        needs_zero_fallback = true;
-                                                        break;
-                                                    }
-                                                }
-                                                scan_off = match scan_off.checked_add(take as u64) {
-                                                    Some(v) => v,
-                                                    None => {
-                                                        needs_zero_fallback = true;
-                                                        break;
-                                                    }
-                                                };
-                                                scan_remaining =
-                                                    scan_remaining.saturating_sub(take as u64);
-                                            }
-                                        }
+    fn num_queues(&self) -> u16 {
+        self.options.num_queues
+    }
 
-                                        if needs_zero_fallback && status == VIRTIO_BLK_S_OK {
-                                            let mut cur_off = byte_off;
-                                            let mut remaining = byte_len;
-                                            while remaining != 0 {
-                                                let take =
-                                                    remaining.min(zero_buf.len() as u64) as usize;
-                                                if self
-                                                    .disk
-                                                    .write_at(cur_off, &zero_buf[..take])
-                                                    .is_err()
-                                                {
-                                                    status = VIRTIO_BLK_S_IOERR;
-                                                    break 'seg_loop;
-                                                }
-                                                cur_off = match cur_off.checked_add(take as u64) {
-                                                    Some(v) => v,
-                                                    None => {
-                                                        status = VIRTIO_BLK_S_IOERR;
-                                                        break 'seg_loop;
-                                                    }
-                                                };
-                                                remaining = remaining.saturating_sub(take as u64);
-                                            }
-                                        }
-                                    } else {
-                                        let mut cur_off = byte_off;
-                                        let mut remaining = byte_len;
-                                        while remaining != 0 {
-                                            let take =
-                                                remaining.min(zero_buf.len() as u64) as usize;
-                                            if self
-                                                .disk
-                                                .write_at(cur_off, &zero_buf[..take])
-                                                .is_err()
-                                            {
-                                                status = VIRTIO_BLK_S_IOERR;
-                                                break 'seg_loop;
-                                            }
-                                            cur_off = match cur_off.checked_add(take as u64) {
-                                                Some(v) => v,
-                                                None => {
-                                                    status = VIRTIO_BLK_S_IOERR;
-                                                    break 'seg_loop;
-                                                }
-                                            };
-                                            remaining = remaining.saturating_sub(take as u64);
-                                        }
-                                    }
-                                }
-                            }
-                        }
+    fn queue_max_size(&self, _queue: u16) -> u16 {
+        VIRTIO_BLK_QUEUE_MAX_SIZE
+    }
+
+    fn process_queue(
+        &mut self,
+        queue_index: u16,
+        chain: DescriptorChain,
+        _queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        if queue_index >= self.options.num_queues {
+            return Err(VirtioDeviceError::Unsupported);
+        }
+
+        let descs = chain.descriptors();
+        if descs.is_empty() {
+            return Ok(false);
+        }
+
+        let status_desc = descs[descs.len() - 1];
+        let can_write_status = status_desc.is_write_only() && status_desc.len != 0;
+
+        // DoS guard: avoid unbounded per-request work/allocations on pathological descriptor chains
+        // (especially when indirect descriptors are enabled).
+        if descs.len() > VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS {
+            let status = VIRTIO_BLK_S_IOERR;
+            if can_write_status {
+                // Best-effort: if the status buffer is invalid/out-of-bounds, still advance the
+                // used ring so the guest can reclaim the descriptor chain.
+                let _ = write_u8(mem, status_desc.addr, status);
+            }
+            self.complete(queue_index, chain.head_index(), 0);
+            return Ok(false);
+        }
+
+        // If the status descriptor is invalid, treat the whole request as invalid. We still
+        // advance the used ring so the guest can reclaim the descriptors, but we avoid touching
+        // any other guest buffers or backend state.
+        if !can_write_status {
+            self.complete(queue_index, chain.head_index(), 0);
+            return Ok(false);
+        }
+
+        // Read the 16-byte request header.
+        let mut hdr = [0u8; 16];
+        let mut hdr_written = 0usize;
+        let mut d_idx = 0usize;
+        let mut d_off = 0usize;
+        let mut header_ok = true;
+        while hdr_written < hdr.len() {
+            if d_idx >= descs.len().saturating_sub(1) {
+                header_ok = false;
+                break;
+            }
+            let d = descs[d_idx];
+            if d.is_write_only() {
+                header_ok = false;
+                break;
+            }
+            let avail = (d.len as usize).saturating_sub(d_off);
+            if avail == 0 {
+                d_idx += 1;
+                d_off = 0;
+                continue;
+            }
+            let take = avail.min(hdr.len() - hdr_written);
+            let Some(addr) = d.addr.checked_add(d_off as u64) else {
+                header_ok = false;
+                break;
+            };
+            let Ok(()) = mem.read(addr, &mut hdr[hdr_written..hdr_written + take]) else {
+                header_ok = false;
+                break;
+            };
+            hdr_written += take;
+            d_off += take;
+            if d_off == d.len as usize {
+                d_idx += 1;
+                d_off = 0;
+            }
+        }
+
+        if !header_ok {
+            // Header is malformed (short, out-of-bounds, or wrong direction). Fail the request
+            // without scanning the remaining descriptors so malformed chains can't force extra
+            // work beyond the header read.
+            if can_write_status {
+                let _ = write_u8(mem, status_desc.addr, VIRTIO_BLK_S_IOERR);
+            }
+            self.complete(queue_index, chain.head_index(), 0);
+            return Ok(false);
+        }
+
+        // Build data segments (everything between header cursor and status descriptor), enforcing
+        // a per-request payload cap to avoid unbounded allocations or work.
+        let mut data_segs: Vec<(crate::queue::Descriptor, usize, usize)> = Vec::new();
+        let mut total_data_len: u64 = 0;
+        let mut data_len_ok = true;
+        let data_end_idx = descs.len().saturating_sub(1);
+        let mut seg_idx = d_idx;
+        let mut seg_off = d_off;
+        while seg_idx < data_end_idx {
+            let d = descs[seg_idx];
+            let d_len = d.len as usize;
+            if seg_off > d_len {
+                data_len_ok = false;
+                break;
+            }
+            let seg_len = d_len - seg_off;
+            if seg_len != 0 {
+                let seg_len_u64 = u64::try_from(seg_len).unwrap_or(u64::MAX);
+                total_data_len = match total_data_len.checked_add(seg_len_u64) {
+                    Some(v) => v,
+                    None => {
+                        data_len_ok = false;
+                        break;
                     }
+                };
+                if total_data_len > VIRTIO_BLK_MAX_REQUEST_DATA_BYTES {
+                    data_len_ok = false;
+                    break;
                 }
-                _ => status = VIRTIO_BLK_S_UNSUPP,
+                data_segs.push((d, seg_off, seg_len));
             }
+            seg_idx += 1;
+            seg_off = 0;
+        }
+
+        let mut status = VIRTIO_BLK_S_IOERR;
+        let mut delay_ns = 0;
+        if data_len_ok {
+            let typ = u32::from_le_bytes(hdr[0..4].try_into().unwrap());
+            let sector = u64::from_le_bytes(hdr[8..16].try_into().unwrap());
+
+            let request = storage_request(typ, sector, total_data_len);
+            let outcome = match (self.fault_injector.as_mut(), request) {
+                (Some(injector), Some(req)) => injector.on_request(req),
+                _ => Default::default(),
+            };
+            delay_ns = outcome.delay_ns;
+            let kind = request.map(|req| req.kind);
+            status = match outcome.error {
+                Some(_) => VIRTIO_BLK_S_IOERR,
+                None if kind == Some(StorageRequestKind::Flush)
+                    && (self.features & VIRTIO_BLK_F_FLUSH) != 0 =>
+                {
+                    // Completed (status included) once the batched flush has finished.
+                    self.held_flushes.push(HeldFlush {
+                        queue_index,
+                        head_index: chain.head_index(),
+                        status_addr: status_desc.addr,
+                        delay_ns,
+                    });
+                    self.barrier_needed = true;
+                    return Ok(false);
+                }
+                None if kind == Some(StorageRequestKind::Write)
+                    && std::mem::take(&mut self.barrier_needed)
+                    && self.disk.write_barrier().is_err() =>
+                {
+                    VIRTIO_BLK_S_IOERR
+                }
+                None => self.execute_request(typ, sector, data_segs, total_data_len, mem),
+            };
         }
 
         // Best-effort: if the status buffer is invalid/out-of-bounds, still advance the used ring
@@ -998,12 +1170,52 @@ impl VirtioDevice for VirtioBlk {
             let _ = write_u8(mem, status_desc.addr, status);
         }
 
-        queue
+        self.complete(queue_index, chain.head_index(), delay_ns);
+        Ok(false)
+    }
+
+    fn poll_queue(
+        &mut self,
+        queue_index: u16,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        self.commit_held_flushes(mem);
+        if self.pending.is_empty() {
+            return Ok(false);
+        }
+        let now_ns = self.now_ns;
+        let release_all = self.fault_injector.is_none();
+        let (mut due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|c| c.queue_index == queue_index && (release_all || c.due_ns <= now_ns));
+        self.pending = pending;
+        if due.is_empty() {
+            return Ok(false);
+        }
+        // Stable sort: equal due times complete in submission order.
+        due.sort_by_key(|c| c.due_ns);
+        let used: Vec<(u16, u32)> = due
+            .iter()
             // Contract v1: virtio-blk drivers must not depend on used lengths.
-            .add_used(mem, chain.head_index(), 0)
+            .map(|c| (c.head_index, 0))
+            .collect();
+        queue
+            .add_used_batch(mem, &used)
             .map_err(|_| VirtioDeviceError::IoError)
     }
 
+    fn queue_has_device_work(&self, queue_index: u16) -> bool {
+        let release_all = self.fault_injector.is_none();
+        self.held_flushes
+            .iter()
+            .any(|held| held.queue_index == queue_index)
+            || self
+                .pending
+                .iter()
+                .any(|c| c.queue_index == queue_index && (release_all || c.due_ns <= self.now_ns))
+    }
+
     fn read_config(&self, offset: u64, data: &mut [u8]) {
         self.config.read(offset, data);
     }
@@ -1014,6 +1226,79 @@ impl VirtioDevice for VirtioBlk {
 
     fn reset(&mut self) {
         self.features = 0;
+        self.pending.clear();
+        self.held_flushes.clear();
+        self.barrier_needed = false;
+    }
+
+    fn snapshot_device_state(&self) -> Option<Vec<u8>> {
+        // - byte0: version
+        // - bytes1..9: now_ns
+        // - byte9: barrier_needed
+        // - u16 count + (queue u16, head u16, due_ns u64) per pending completion
+        // - u16 count + (queue u16, head u16, status_addr u64, delay_ns u64) per held FLUSH
+        //
+        // Popped requests whose used-ring entries are still pending must survive restore, or the
+        // guest would wait on them forever.
+        let mut out = vec![1];
+        out.extend_from_slice(&self.now_ns.to_le_bytes());
+        out.push(u8::from(self.barrier_needed));
+        out.extend_from_slice(&(self.pending.len() as u16).to_le_bytes());
+        for c in &self.pending {
+            out.extend_from_slice(&c.queue_index.to_le_bytes());
+            out.extend_from_slice(&c.head_index.to_le_bytes());
+            out.extend_from_slice(&c.due_ns.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.held_flushes.len() as u16).to_le_bytes());
+        for held in &self.held_flushes {
+            out.extend_from_slice(&held.queue_index.to_le_bytes());
+            out.extend_from_slice(&held.head_index.to_le_bytes());
+            out.extend_from_slice(&held.status_addr.to_le_bytes());
+            out.extend_from_slice(&held.delay_ns.to_le_bytes());
+        }
+        Some(out)
+    }
+
+    fn restore_device_state(&mut self, bytes: &[u8]) {
+        fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+            let (head, rest) = bytes.split_first_chunk::<N>()?;
+            *bytes = rest;
+            Some(*head)
+        }
+        fn parse(mut bytes: &[u8]) -> Option<(u64, bool, Vec<PendingCompletion>, Vec<HeldFlush>)> {
+            if take::<1>(&mut bytes)? != [1] {
+                return None;
+            }
+            let now_ns = u64::from_le_bytes(take(&mut bytes)?);
+            let barrier_needed = take::<1>(&mut bytes)?[0] != 0;
+            let count = u16::from_le_bytes(take(&mut bytes)?);
+            let mut pending = Vec::new();
+            for _ in 0..count {
+                pending.push(PendingCompletion {
+                    queue_index: u16::from_le_bytes(take(&mut bytes)?),
+                    head_index: u16::from_le_bytes(take(&mut bytes)?),
+                    due_ns: u64::from_le_bytes(take(&mut bytes)?),
+                });
+            }
+            let count = u16::from_le_bytes(take(&mut bytes)?);
+            let mut held_flushes = Vec::new();
+            for _ in 0..count {
+                held_flushes.push(HeldFlush {
+                    queue_index: u16::from_le_bytes(take(&mut bytes)?),
+                    head_index: u16::from_le_bytes(take(&mut bytes)?),
+                    status_addr: u64::from_le_bytes(take(&mut bytes)?),
+                    delay_ns: u64::from_le_bytes(take(&mut bytes)?),
+                });
+            }
+            Some((now_ns, barrier_needed, pending, held_flushes))
+        }
+
+        if let Some((now_ns, barrier_needed, pending, held_flushes)) = parse(bytes) {
+            self.now_ns = now_ns;
+            self.barrier_needed = barrier_needed;
+            self.pending = pending;
+            self.held_flushes = held_flushes;
+        }
     }
 
     fn as_any(&self) -> &dyn core::any::Any {
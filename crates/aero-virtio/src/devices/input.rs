@@ -35,6 +35,10 @@ pub const REL_WHEEL: u16 = 0x08;
 pub const ABS_X: u16 = 0x00;
 pub const ABS_Y: u16 = 0x01;
 
+/// Inclusive `ABS_X`/`ABS_Y` range advertised by the tablet (`VIRTIO_INPUT_CFG_ABS_INFO`).
+pub const TABLET_ABS_MIN: i32 = 0;
+pub const TABLET_ABS_MAX: i32 = 32767;
+
 pub const LED_NUML: u16 = 0x00;
 pub const LED_CAPSL: u16 = 0x01;
 pub const LED_SCROLLL: u16 = 0x02;
@@ -447,6 +451,13 @@ impl VirtioInputBitmaps {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct PendingEvent {
+    event: VirtioInputEvent,
+    // See `VirtioInput::events_queued`.
+    number: u64,
+}
+
 pub struct VirtioInput {
     kind: VirtioInputDeviceKind,
     name: String,
@@ -456,8 +467,10 @@ pub struct VirtioInput {
     config_subsel: u8,
     bitmaps: VirtioInputBitmaps,
 
-    pending: VecDeque<VirtioInputEvent>,
+    pending: VecDeque<PendingEvent>,
     buffers: VecDeque<DescriptorChain>,
+    events_queued: u64,
+    last_delivered_event: u64,
 
     /// Keyboard LED state as last reported by the guest driver via `statusq` (queue 1).
     ///
@@ -515,6 +528,8 @@ impl VirtioInput {
             bitmaps,
             pending: VecDeque::new(),
             buffers: VecDeque::new(),
+            events_queued: 0,
+            last_delivered_event: 0,
             leds_mask: 0,
         }
     }
@@ -523,7 +538,11 @@ impl VirtioInput {
         if self.pending.len() >= MAX_PENDING_EVENTS {
             self.pending.pop_front();
         }
-        self.pending.push_back(event);
+        self.events_queued += 1;
+        self.pending.push_back(PendingEvent {
+            event,
+            number: self.events_queued,
+        });
     }
 
     pub fn inject_key(&mut self, code: u16, pressed: bool) {
@@ -606,6 +625,36 @@ impl VirtioInput {
         });
     }
 
+    /// Inject relative motion and both wheel axes as a single frame terminated by one
+    /// `SYN_REPORT`. Zero axes are omitted; an all-zero update emits nothing.
+    ///
+    /// Used by host-side coalescing, which merges many small updates into one report.
+    pub fn inject_rel_frame(&mut self, dx: i32, dy: i32, wheel: i32, hwheel: i32) {
+        let axes = [
+            (REL_X, dx),
+            (REL_Y, dy),
+            (REL_WHEEL, wheel),
+            (REL_HWHEEL, hwheel),
+        ];
+        if axes.iter().all(|&(_, value)| value == 0) {
+            return;
+        }
+        for (code, value) in axes {
+            if value != 0 {
+                self.push_event(VirtioInputEvent {
+                    type_: EV_REL,
+                    code,
+                    value,
+                });
+            }
+        }
+        self.push_event(VirtioInputEvent {
+            type_: EV_SYN,
+            code: SYN_REPORT,
+            value: 0,
+        });
+    }
+
     pub fn inject_button(&mut self, code: u16, pressed: bool) {
         if code == 0 {
             return;
@@ -647,6 +696,36 @@ impl VirtioInput {
         self.pending.len()
     }
 
+    /// Number of events that can still be queued before [`VirtioInput::push_event`] starts
+    /// evicting the oldest pending event.
+    pub fn pending_events_free(&self) -> usize {
+        MAX_PENDING_EVENTS.saturating_sub(self.pending.len())
+    }
+
+    /// Remove the oldest queued frame (events up to and including a `SYN_REPORT`) that consists
+    /// only of `EV_REL` events. Returns whether such a frame was found.
+    ///
+    /// This lets hosts make room for new input without discarding button or key transitions.
+    pub fn drop_oldest_rel_frame(&mut self) -> bool {
+        let mut start = 0usize;
+        let mut rel_only = true;
+        for idx in 0..self.pending.len() {
+            match self.pending[idx].event.type_ {
+                EV_SYN => {
+                    if rel_only && idx > start {
+                        self.pending.drain(start..=idx);
+                        return true;
+                    }
+                    start = idx + 1;
+                    rel_only = true;
+                }
+                EV_REL => {}
+                _ => rel_only = false,
+            }
+        }
+        false
+    }
+
     pub fn kind(&self) -> VirtioInputDeviceKind {
         self.kind
     }
@@ -670,6 +749,19 @@ impl VirtioInput {
     pub fn pending_len(&self) -> usize {
         self.pending.len()
     }
+
+    /// Total number of events queued so far, including events later evicted or dropped before
+    /// delivery. Events are numbered from 1 in queueing order, so this is also the number of the
+    /// most recently queued event.
+    pub fn events_queued(&self) -> u64 {
+        self.events_queued
+    }
+
+    /// Number (see [`VirtioInput::events_queued`]) of the latest event written into a guest
+    /// buffer and completed into the used ring, or 0 if none has been delivered yet.
+    pub fn last_delivered_event(&self) -> u64 {
+        self.last_delivered_event
+    }
 }
 
 impl Default for VirtioInput {
@@ -842,7 +934,7 @@ impl VirtioInput {
     ) -> Result<bool, VirtioDeviceError> {
         let mut need_irq = false;
         while let Some(chain) = self.buffers.pop_front() {
-            let Some(event) = self.pending.pop_front() else {
+            let Some(PendingEvent { event, number }) = self.pending.pop_front() else {
                 self.buffers.push_front(chain);
                 break;
             };
@@ -870,6 +962,7 @@ impl VirtioInput {
             need_irq |= queue
                 .add_used(mem, chain.head_index(), written as u32)
                 .map_err(|_| VirtioDeviceError::IoError)?;
+            self.last_delivered_event = number;
         }
 
         Ok(need_irq)
@@ -918,13 +1011,10 @@ impl VirtioInput {
 
                 // For Aero contract v1, expose an absolute coordinate range matching the HID
                 // logical range used by the Win7 virtio-input tablet HID descriptor.
-                const DEFAULT_MIN: i32 = 0;
-                const DEFAULT_MAX: i32 = 32767;
-
                 let abs = match u16::from(self.config_subsel) {
                     ABS_X | ABS_Y => Some(VirtioInputAbsInfo {
-                        min: DEFAULT_MIN,
-                        max: DEFAULT_MAX,
+                        min: TABLET_ABS_MIN,
+                        max: TABLET_ABS_MAX,
                         fuzz: 0,
                         flat: 0,
                         res: 0,
@@ -1072,7 +1162,7 @@ mod tests {
             3,
             "inject_wheel2 should emit REL_WHEEL, REL_HWHEEL, and a single SYN_REPORT"
         );
-        let events: Vec<VirtioInputEvent> = dev.pending.iter().copied().collect();
+        let events: Vec<VirtioInputEvent> = dev.pending.iter().map(|p| p.event).collect();
         assert_eq!(
             events,
             vec![
@@ -1095,6 +1185,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn drop_oldest_rel_frame_keeps_button_frames() {
+        let mut dev = VirtioInput::new(VirtioInputDeviceKind::Mouse);
+        dev.inject_button(BTN_LEFT, true);
+        dev.inject_rel_frame(1, 2, 0, 0);
+        dev.inject_button(BTN_LEFT, false);
+        dev.inject_rel_frame(3, 0, 1, 0);
+
+        assert!(dev.drop_oldest_rel_frame());
+        let events: Vec<(u16, u16, i32)> = dev
+            .pending
+            .iter()
+            .map(|p| (p.event.type_, p.event.code, p.event.value))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (EV_KEY, BTN_LEFT, 1),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_KEY, BTN_LEFT, 0),
+                (EV_SYN, SYN_REPORT, 0),
+                (EV_REL, REL_X, 3),
+                (EV_REL, REL_WHEEL, 1),
+                (EV_SYN, SYN_REPORT, 0),
+            ]
+        );
+
+        assert!(dev.drop_oldest_rel_frame());
+        assert!(!dev.drop_oldest_rel_frame());
+        assert_eq!(dev.pending.len(), 4);
+    }
+
     #[test]
     fn inject_rel_move_is_noop_when_deltas_are_zero() {
         let mut dev = VirtioInput::new(VirtioInputDeviceKind::Mouse);
@@ -1133,9 +1255,9 @@ mod tests {
         }
 
         assert_eq!(dev.pending.len(), MAX_PENDING_EVENTS);
-        assert_eq!(dev.pending.front().unwrap().value, 100);
+        assert_eq!(dev.pending.front().unwrap().event.value, 100);
         assert_eq!(
-            dev.pending.back().unwrap().value,
+            dev.pending.back().unwrap().event.value,
             (MAX_PENDING_EVENTS + 99) as i32
         );
     }
@@ -1209,4 +1331,56 @@ mod tests {
             "extra event buffers should be completed with used.len=0 once the internal queue is full"
         );
     }
+
+    #[test]
+    fn delivered_event_numbers_survive_dropped_frames() {
+        let mut dev = VirtioInput::new(VirtioInputDeviceKind::Mouse);
+        let mut mem = GuestRam::new(0x10000);
+
+        let desc_table = 0x1000;
+        let avail = 0x2000;
+        let used = 0x3000;
+        let mut queue = VirtQueue::new(
+            VirtQueueConfig {
+                size: 8,
+                desc_addr: desc_table,
+                avail_addr: avail,
+                used_addr: used,
+            },
+            false,
+        )
+        .unwrap();
+
+        // Events 1..=3 form a REL-only frame, 4..=5 a button frame.
+        dev.inject_rel_frame(1, 2, 0, 0);
+        dev.inject_button(BTN_LEFT, true);
+        assert_eq!(dev.events_queued(), 5);
+        assert!(dev.drop_oldest_rel_frame());
+        assert_eq!(dev.last_delivered_event(), 0);
+
+        for i in 0..2u16 {
+            write_desc(
+                &mut mem,
+                desc_table,
+                i,
+                0x4000 + u64::from(i) * 0x100,
+                8,
+                VIRTQ_DESC_F_WRITE,
+                0,
+            );
+            write_u16_le(&mut mem, avail + 4 + u64::from(i) * 2, i).unwrap();
+        }
+        write_u16_le(&mut mem, avail + 2, 2).unwrap();
+        for _ in 0..2 {
+            let PoppedDescriptorChain::Chain(chain) =
+                queue.pop_descriptor_chain(&mem).unwrap().unwrap()
+            else {
+                panic!("unexpected descriptor chain parse error");
+            };
+            dev.process_queue(0, chain, &mut queue, &mut mem).unwrap();
+        }
+
+        assert_eq!(read_u16_le(&mem, used + 2).unwrap(), 2);
+        assert_eq!(dev.last_delivered_event(), 5);
+    }
 }
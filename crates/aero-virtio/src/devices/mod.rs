@@ -2,11 +2,13 @@ use crate::memory::GuestMemory;
 use crate::queue::{DescriptorChain, VirtQueue};
 use core::any::Any;
 
+pub mod balloon;
 pub mod blk;
 pub mod gpu;
 pub mod input;
 pub mod net;
 pub mod net_offload;
+pub mod rng;
 #[cfg(feature = "snd")]
 pub mod snd;
 
@@ -87,6 +89,15 @@ pub trait VirtioDevice: Any {
         Ok(false)
     }
 
+    /// Whether [`VirtioDevice::poll_queue`] has work for `queue_index` that does not depend on new
+    /// avail entries (e.g. completions held back by storage latency injection).
+    ///
+    /// [`crate::pci::VirtioPciDevice::process_notified_queues`] services such queues even when the
+    /// guest did not notify them.
+    fn queue_has_device_work(&self, _queue_index: u16) -> bool {
+        false
+    }
+
     /// Read from device-specific configuration space.
     fn read_config(&self, offset: u64, data: &mut [u8]);
 
@@ -0,0 +1,389 @@
+use crate::devices::{VirtioDevice, VirtioDeviceError};
+use crate::memory::GuestMemory;
+use crate::pci::{VIRTIO_F_RING_INDIRECT_DESC, VIRTIO_F_VERSION_1};
+use crate::queue::{DescriptorChain, VirtQueue};
+use std::collections::VecDeque;
+
+pub const VIRTIO_DEVICE_TYPE_RNG: u16 = 4;
+
+/// Virtqueue index of the single request queue.
+pub const VIRTIO_RNG_QUEUE_REQUEST: u16 = 0;
+
+/// Default number of entropy bytes handed to the guest per [`VirtioRng::refill_budget`] call.
+///
+/// Buffers that do not fit in the budget stay queued until the next refill, so a driver that posts
+/// huge or many buffers cannot turn one platform tick into unbounded work.
+pub const VIRTIO_RNG_DEFAULT_BYTES_PER_TICK: usize = 4096;
+
+/// A source of bytes for the virtio-rng device.
+pub trait EntropySource {
+    /// Fill `buf` completely.
+    fn fill_bytes(&mut self, buf: &mut [u8]);
+}
+
+/// Deterministic ChaCha20 keystream seeded from a 64-bit seed.
+///
+/// The key is the little-endian seed followed by zero bytes, with a zero nonce and a 64-bit block
+/// counter, so the stream is fully described by `(seed, position)` and can be snapshotted and
+/// resumed bit-exactly.
+#[derive(Debug, Clone)]
+pub struct ChaChaEntropySource {
+    seed: u64,
+    /// Bytes consumed since the start of the stream.
+    position: u64,
+    block: [u8; 64],
+    /// Index of the block cached in `block`, if any.
+    block_index: Option<u64>,
+}
+
+impl ChaChaEntropySource {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            position: 0,
+            block: [0; 64],
+            block_index: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Bytes consumed since the start of the stream.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Continue the stream at byte `position`.
+    pub fn seek(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    fn key(&self) -> [u32; 8] {
+        let mut key = [0u32; 8];
+        key[0] = self.seed as u32;
+        key[1] = (self.seed >> 32) as u32;
+        key
+    }
+}
+
+impl EntropySource for ChaChaEntropySource {
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let index = self.position / 64;
+            if self.block_index != Some(index) {
+                self.block = chacha20_block(&self.key(), index, 0);
+                self.block_index = Some(index);
+            }
+            let offset = (self.position % 64) as usize;
+            let take = (64 - offset).min(buf.len() - filled);
+            buf[filled..filled + take].copy_from_slice(&self.block[offset..offset + take]);
+            filled += take;
+            self.position = self.position.wrapping_add(take as u64);
+        }
+    }
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// One ChaCha20 block (RFC 8439 §2.3) with the original 64-bit counter / 64-bit nonce layout.
+fn chacha20_block(key: &[u32; 8], counter: u64, nonce: u64) -> [u8; 64] {
+    let mut state = [0u32; 16];
+    // "expand 32-byte k"
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    state[4..12].copy_from_slice(key);
+    state[12] = counter as u32;
+    state[13] = (counter >> 32) as u32;
+    state[14] = nonce as u32;
+    state[15] = (nonce >> 32) as u32;
+
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; 64];
+    for (i, word) in working.iter().enumerate() {
+        let word = word.wrapping_add(state[i]);
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+/// Virtio entropy device.
+///
+/// Guest buffers are filled from a deterministic [`ChaChaEntropySource`] unless the host installs
+/// its own source ([`VirtioRng::set_host_source`]). Filling is bounded per tick: the platform calls
+/// [`VirtioRng::refill_budget`] before processing the queue, and buffers beyond the budget are held
+/// until a later tick.
+pub struct VirtioRng {
+    features: u64,
+    deterministic: ChaChaEntropySource,
+    host_source: Option<Box<dyn EntropySource>>,
+    buffers: VecDeque<DescriptorChain>,
+    bytes_per_tick: usize,
+    budget: usize,
+}
+
+impl VirtioRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            features: 0,
+            deterministic: ChaChaEntropySource::new(seed),
+            host_source: None,
+            buffers: VecDeque::new(),
+            bytes_per_tick: VIRTIO_RNG_DEFAULT_BYTES_PER_TICK,
+            budget: VIRTIO_RNG_DEFAULT_BYTES_PER_TICK,
+        }
+    }
+
+    /// Replace the deterministic stream with a host-provided source (`None` switches back).
+    ///
+    /// Host sources are not replayable: snapshots only record that one was installed.
+    pub fn set_host_source(&mut self, source: Option<Box<dyn EntropySource>>) {
+        self.host_source = source;
+    }
+
+    pub fn has_host_source(&self) -> bool {
+        self.host_source.is_some()
+    }
+
+    /// The deterministic stream (used whenever no host source is installed).
+    pub fn deterministic_source(&self) -> &ChaChaEntropySource {
+        &self.deterministic
+    }
+
+    pub fn set_bytes_per_tick(&mut self, bytes: usize) {
+        self.bytes_per_tick = bytes;
+        self.budget = self.budget.min(bytes);
+    }
+
+    /// Start a new tick: allow up to the per-tick byte budget to be handed to the guest.
+    pub fn refill_budget(&mut self) {
+        self.budget = self.bytes_per_tick;
+    }
+
+    /// Number of guest buffers waiting for budget.
+    pub fn pending_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        match self.host_source.as_mut() {
+            Some(source) => source.fill_bytes(buf),
+            None => self.deterministic.fill_bytes(buf),
+        }
+    }
+
+    fn flush(
+        &mut self,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        let mut need_irq = false;
+        while self.budget != 0 {
+            let Some(chain) = self.buffers.pop_front() else {
+                break;
+            };
+
+            let mut written = 0usize;
+            let mut bytes = Vec::new();
+            for d in chain.descriptors() {
+                if !d.is_write_only() {
+                    return Err(VirtioDeviceError::BadDescriptorChain);
+                }
+                let take = (d.len as usize).min(self.budget - written);
+                if take == 0 {
+                    break;
+                }
+                bytes.resize(take, 0);
+                self.fill(&mut bytes);
+                mem.write(d.addr, &bytes)
+                    .map_err(|_| VirtioDeviceError::IoError)?;
+                written += take;
+            }
+            self.budget -= written;
+
+            need_irq |= queue
+                .add_used(mem, chain.head_index(), written as u32)
+                .map_err(|_| VirtioDeviceError::IoError)?;
+        }
+        Ok(need_irq)
+    }
+}
+
+impl VirtioDevice for VirtioRng {
+    fn device_type(&self) -> u16 {
+        VIRTIO_DEVICE_TYPE_RNG
+    }
+
+    fn device_features(&self) -> u64 {
+        VIRTIO_F_VERSION_1 | VIRTIO_F_RING_INDIRECT_DESC
+    }
+
+    fn set_features(&mut self, features: u64) {
+        self.features = features;
+    }
+
+    fn num_queues(&self) -> u16 {
+        1
+    }
+
+    fn queue_max_size(&self, _queue: u16) -> u16 {
+        64
+    }
+
+    fn process_queue(
+        &mut self,
+        queue_index: u16,
+        chain: DescriptorChain,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        if queue_index != VIRTIO_RNG_QUEUE_REQUEST {
+            return Err(VirtioDeviceError::Unsupported);
+        }
+        // A correct driver cannot have more outstanding buffers than the queue size; complete any
+        // excess immediately (with no data) rather than growing without bound.
+        let max_buffers = queue.size() as usize;
+        if max_buffers != 0 && self.buffers.len() >= max_buffers {
+            return queue
+                .add_used(mem, chain.head_index(), 0)
+                .map_err(|_| VirtioDeviceError::IoError);
+        }
+        self.buffers.push_back(chain);
+        self.flush(queue, mem)
+    }
+
+    fn poll_queue(
+        &mut self,
+        queue_index: u16,
+        queue: &mut VirtQueue,
+        mem: &mut dyn GuestMemory,
+    ) -> Result<bool, VirtioDeviceError> {
+        if queue_index != VIRTIO_RNG_QUEUE_REQUEST {
+            return Ok(false);
+        }
+        self.flush(queue, mem)
+    }
+
+    fn read_config(&self, _offset: u64, data: &mut [u8]) {
+        // virtio-rng has no device-specific configuration.
+        data.fill(0);
+    }
+
+    fn write_config(&mut self, _offset: u64, _data: &[u8]) {}
+
+    fn reset(&mut self) {
+        // The entropy sources (and the deterministic stream position) are host state and survive
+        // device resets, so a rebooted guest does not see the same bytes again.
+        self.features = 0;
+        self.buffers.clear();
+        self.budget = self.bytes_per_tick;
+    }
+
+    fn snapshot_device_state(&self) -> Option<Vec<u8>> {
+        // - byte0: version
+        // - byte1: 1 if a host source was installed
+        // - bytes2..10: deterministic seed
+        // - bytes10..18: deterministic stream position
+        //
+        // Held buffers are runtime-only; platforms rewind the request queue on restore so the
+        // transport re-pops them.
+        let mut out = vec![1, u8::from(self.host_source.is_some())];
+        out.extend_from_slice(&self.deterministic.seed().to_le_bytes());
+        out.extend_from_slice(&self.deterministic.position().to_le_bytes());
+        Some(out)
+    }
+
+    fn restore_device_state(&mut self, bytes: &[u8]) {
+        if bytes.len() < 18 || bytes[0] != 1 {
+            return;
+        }
+        // Whether a host source was installed is informational: host sources cannot be recreated
+        // from a snapshot, so whatever source the host has installed now stays in place.
+        let seed = u64::from_le_bytes(bytes[2..10].try_into().unwrap());
+        let position = u64::from_le_bytes(bytes[10..18].try_into().unwrap());
+        self.deterministic = ChaChaEntropySource::new(seed);
+        self.deterministic.seek(position);
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chacha20_block_matches_rfc8439_test_vector() {
+        // RFC 8439 §2.3.2: key 00..1f, block counter 1, nonce 00:00:00:09:00:00:00:4a:00:00:00:00.
+        let mut key = [0u32; 8];
+        for (i, word) in key.iter_mut().enumerate() {
+            let b = (i * 4) as u8;
+            *word = u32::from_le_bytes([b, b + 1, b + 2, b + 3]);
+        }
+        let block = chacha20_block(&key, 0x0900_0000_0000_0001, 0x4a00_0000);
+        assert_eq!(
+            block[..16],
+            [
+                0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20,
+                0x71, 0xc4
+            ]
+        );
+        assert_eq!(
+            block[48..],
+            [
+                0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16, 0x4e, 0xb9, 0xcb, 0xd0, 0x83, 0xe8, 0xa2, 0x50,
+                0x3c, 0x4e
+            ]
+        );
+    }
+
+    #[test]
+    fn chacha_stream_resumes_at_any_position() {
+        let mut whole = ChaChaEntropySource::new(42);
+        let mut expected = [0u8; 200];
+        whole.fill_bytes(&mut expected);
+        assert_eq!(whole.position(), 200);
+        assert_eq!(expected[..4], [0x1f, 0x76, 0xe5, 0x26]);
+
+        for split in [0, 1, 63, 64, 65, 150] {
+            let mut src = ChaChaEntropySource::new(42);
+            let mut head = vec![0u8; split];
+            src.fill_bytes(&mut head);
+
+            let mut resumed = ChaChaEntropySource::new(42);
+            resumed.seek(src.position());
+            let mut tail = vec![0u8; 200 - split];
+            resumed.fill_bytes(&mut tail);
+
+            assert_eq!(head, expected[..split]);
+            assert_eq!(tail, expected[split..]);
+        }
+    }
+}
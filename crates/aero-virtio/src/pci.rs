@@ -880,7 +880,7 @@ impl VirtioPciDevice {
                 // In addition to explicit notify writes, treat any queue with unconsumed avail
                 // entries as pending. This makes snapshot/restore robust when a snapshot is taken
                 // after the guest posts buffers but before the platform processes the notify.
-                if q.pending_notify {
+                if q.pending_notify || self.device.queue_has_device_work(queue_index as u16) {
                     return true;
                 }
 
@@ -926,7 +926,7 @@ impl VirtioPciDevice {
                 // In addition to explicit notify writes, treat any queue with unconsumed avail
                 // entries as pending. This makes snapshot/restore robust when a snapshot is taken
                 // after the guest posts buffers but before the platform processes the notify.
-                if q.pending_notify {
+                if q.pending_notify || self.device.queue_has_device_work(queue_index as u16) {
                     return true;
                 }
 
@@ -1012,6 +1012,10 @@ impl VirtioPciDevice {
             1 => (0x02, 0x00),
             // Mass storage / SCSI (commonly used for virtio-blk).
             2 => (0x01, 0x00),
+            // Unclassified device (virtio-rng, virtio-balloon; matches QEMU).
+            4 | 5 => (0xff, 0x00),
+            // Mass storage / other (virtio-9p).
+            9 => (0x01, 0x80),
             // Display controller / other (virtio-gpu).
             16 => (0x03, 0x80),
             // Input device controller / other.
@@ -1694,8 +1698,9 @@ impl VirtioPciDevice {
         self.legacy_irq_pending = state.legacy_intx_level;
         self.sync_legacy_irq_line();
 
-        // Snapshot schema is modern-only. If the guest had started driver initialization, ensure we
-        // continue to reject legacy register accesses after restore.
+        // The base snapshot schema is modern-only; `load_state` switches back to legacy mode when
+        // the snapshot carries legacy transport state. Otherwise, if the guest had started driver
+        // initialization, ensure we continue to reject legacy register accesses after restore.
         self.transport_mode = if self.device_status == 0 {
             TransportMode::Unknown
         } else {
@@ -1754,7 +1759,8 @@ impl IoSnapshot for VirtioPciDevice {
     const DEVICE_ID: [u8; 4] = *b"VPCI";
     // v1.1: includes MSI-X table + PBA state in addition to PCI config + virtio transport.
     // v1.2: adds an optional device-specific snapshot blob (e.g. virtio-input keyboard LED state).
-    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 2);
+    // v1.3: adds optional legacy (virtio 0.9) transport state for drivers bound via the I/O BAR.
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 3);
 
     fn save_state(&self) -> Vec<u8> {
         const TAG_PCI_CONFIG: u16 = 1;
@@ -1763,6 +1769,7 @@ impl IoSnapshot for VirtioPciDevice {
         const TAG_MSIX_TABLE: u16 = 4;
         const TAG_MSIX_PBA: u16 = 5;
         const TAG_DEVICE_STATE: u16 = 6;
+        const TAG_LEGACY_TRANSPORT: u16 = 7;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -1772,6 +1779,15 @@ impl IoSnapshot for VirtioPciDevice {
         if let Some(state) = self.device.snapshot_device_state() {
             w.field_bytes(TAG_DEVICE_STATE, state);
         }
+        if self.transport_mode == TransportMode::Legacy {
+            // The modern transport schema has no notion of queue PFNs; record them (and the fact
+            // that the driver bound through the legacy BAR) separately.
+            let mut legacy = Vec::with_capacity(self.queues.len() * 4);
+            for q in &self.queues {
+                legacy.extend_from_slice(&q.legacy_pfn.to_le_bytes());
+            }
+            w.field_bytes(TAG_LEGACY_TRANSPORT, legacy);
+        }
 
         if let Some(msix) = self.config.capability::<MsixCapability>() {
             w.field_bytes(TAG_MSIX_TABLE, msix.snapshot_table().to_vec());
@@ -1793,6 +1809,7 @@ impl IoSnapshot for VirtioPciDevice {
         const TAG_MSIX_TABLE: u16 = 4;
         const TAG_MSIX_PBA: u16 = 5;
         const TAG_DEVICE_STATE: u16 = 6;
+        const TAG_LEGACY_TRANSPORT: u16 = 7;
 
         let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
         r.ensure_device_major(Self::DEVICE_VERSION.major)?;
@@ -1833,6 +1850,23 @@ impl IoSnapshot for VirtioPciDevice {
         let transport = SnapshotVirtioPciTransportState::decode(buf)?;
         self.restore_transport_state(&transport);
 
+        if let Some(buf) = r.bytes(TAG_LEGACY_TRANSPORT) {
+            if !self.legacy_io_enabled {
+                return Err(SnapshotError::InvalidFieldEncoding(
+                    "snapshot contains legacy virtio state but device has no legacy I/O BAR",
+                ));
+            }
+            if buf.len() % 4 != 0 || buf.len() / 4 > self.queues.len() {
+                return Err(SnapshotError::InvalidFieldEncoding(
+                    "invalid legacy virtio transport state length",
+                ));
+            }
+            for (q, pfn) in self.queues.iter_mut().zip(buf.chunks_exact(4)) {
+                q.legacy_pfn = u32::from_le_bytes(pfn.try_into().unwrap());
+            }
+            self.transport_mode = TransportMode::Legacy;
+        }
+
         if let Some(buf) = r.bytes(TAG_DEVICE_STATE) {
             self.device.restore_device_state(buf);
         }
@@ -2192,6 +2226,38 @@ mod tests {
         assert_eq!(pci.device_status(), VIRTIO_STATUS_DRIVER_OK);
     }
 
+    #[test]
+    fn legacy_transport_state_survives_snapshot_restore() {
+        let new_dev = || {
+            let mut pci = VirtioPciDevice::new_transitional(
+                Box::new(CountingDevice::new(8)),
+                Box::new(NoopInterrupts),
+            );
+            pci.set_pci_command(1u16 << 0);
+            pci
+        };
+
+        let mut pci = new_dev();
+        pci.legacy_io_write(VIRTIO_PCI_LEGACY_QUEUE_SEL, &0u16.to_le_bytes());
+        pci.legacy_io_write(VIRTIO_PCI_LEGACY_QUEUE_PFN, &3u32.to_le_bytes());
+        pci.legacy_io_write(VIRTIO_PCI_LEGACY_STATUS, &[VIRTIO_STATUS_DRIVER_OK]);
+
+        let mut restored = new_dev();
+        restored.load_state(&pci.save_state()).unwrap();
+
+        let mut pfn = [0u8; 4];
+        restored.legacy_io_read(VIRTIO_PCI_LEGACY_QUEUE_PFN, &mut pfn);
+        assert_eq!(u32::from_le_bytes(pfn), 3);
+        // The restored device stays bound to the legacy interface.
+        restored.legacy_io_write(VIRTIO_PCI_LEGACY_QUEUE_NOTIFY, &0u16.to_le_bytes());
+        assert!(restored.queues[0].pending_notify);
+
+        // A modern-only device cannot take legacy state.
+        let mut modern =
+            VirtioPciDevice::new(Box::new(CountingDevice::new(8)), Box::new(NoopInterrupts));
+        assert!(modern.load_state(&pci.save_state()).is_err());
+    }
+
     #[test]
     fn interrupt_msix_disabled_uses_intx() {
         let state = Rc::new(RefCell::new(TestInterruptState::default()));
@@ -304,23 +304,37 @@ impl VirtQueue {
         head_index: u16,
         len: u32,
     ) -> Result<bool, VirtQueueError> {
+        self.add_used_batch(mem, &[(head_index, len)])
+    }
+
+    /// Publish several `(head_index, len)` used entries with a single `used.idx` update.
+    ///
+    /// Returns whether the guest should be interrupted for the batch as a whole.
+    pub fn add_used_batch<M: GuestMemory + ?Sized>(
+        &mut self,
+        mem: &mut M,
+        entries: &[(u16, u32)],
+    ) -> Result<bool, VirtQueueError> {
+        if entries.is_empty() {
+            return Ok(false);
+        }
         let old_used = self.next_used;
-        let used_elem_index = old_used % self.config.size;
-        let elem_offset = 4 + u64::from(used_elem_index) * 8;
-        let elem_addr =
-            self.config
-                .used_addr
-                .checked_add(elem_offset)
-                .ok_or(VirtQueueError::GuestMemory(GuestMemoryError::OutOfBounds {
+        for &(head_index, len) in entries {
+            let used_elem_index = self.next_used % self.config.size;
+            let elem_offset = 4 + u64::from(used_elem_index) * 8;
+            let elem_addr = self.config.used_addr.checked_add(elem_offset).ok_or(
+                VirtQueueError::GuestMemory(GuestMemoryError::OutOfBounds {
                     addr: self.config.used_addr,
                     len: 8,
-                }))?;
-        let mut elem_bytes = [0u8; 8];
-        elem_bytes[0..4].copy_from_slice(&u32::from(head_index).to_le_bytes());
-        elem_bytes[4..8].copy_from_slice(&len.to_le_bytes());
-        mem.write(elem_addr, &elem_bytes)?;
+                }),
+            )?;
+            let mut elem_bytes = [0u8; 8];
+            elem_bytes[0..4].copy_from_slice(&u32::from(head_index).to_le_bytes());
+            elem_bytes[4..8].copy_from_slice(&len.to_le_bytes());
+            mem.write(elem_addr, &elem_bytes)?;
+            self.next_used = self.next_used.wrapping_add(1);
+        }
 
-        self.next_used = self.next_used.wrapping_add(1);
         let used_idx_addr =
             self.config
                 .used_addr
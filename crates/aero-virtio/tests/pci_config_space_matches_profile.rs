@@ -3,11 +3,12 @@ use std::collections::BTreeMap;
 use aero_devices::pci::capabilities::PCI_CAP_ID_VENDOR_SPECIFIC;
 use aero_devices::pci::msix::PCI_CAP_ID_MSIX;
 use aero_devices::pci::profile::{
-    PciDeviceProfile, VIRTIO_BLK, VIRTIO_INPUT_KEYBOARD, VIRTIO_INPUT_MOUSE, VIRTIO_INPUT_TABLET,
-    VIRTIO_NET,
+    PciDeviceProfile, VIRTIO_BALLOON, VIRTIO_BLK, VIRTIO_INPUT_KEYBOARD, VIRTIO_INPUT_MOUSE,
+    VIRTIO_INPUT_TABLET, VIRTIO_NET,
 };
 use aero_devices::pci::{PciBarDefinition, PciConfigSpace, PciDevice as _};
 
+use aero_virtio::devices::balloon::VirtioBalloon;
 use aero_virtio::devices::blk::{MemDisk, VirtioBlk};
 use aero_virtio::devices::input::{VirtioInput, VirtioInputDeviceKind};
 use aero_virtio::devices::net::{LoopbackNet, VirtioNet};
@@ -261,6 +262,16 @@ fn virtio_pci_config_space_matches_canonical_profiles() {
         assert_config_space_matches_profile(dev.config_mut(), &mut profile_cfg, VIRTIO_BLK);
     }
 
+    // virtio-balloon
+    {
+        let mut dev = VirtioPciDevice::new(
+            Box::new(VirtioBalloon::new()),
+            Box::new(InterruptLog::default()),
+        );
+        let mut profile_cfg = VIRTIO_BALLOON.build_config_space();
+        assert_config_space_matches_profile(dev.config_mut(), &mut profile_cfg, VIRTIO_BALLOON);
+    }
+
     // virtio-input keyboard (function 0, multi-function header type)
     {
         let mut dev = VirtioPciDevice::new(
@@ -0,0 +1,269 @@
+use aero_virtio::devices::balloon::{
+    VirtioBalloon, VirtioBalloonEvent, VirtioBalloonStats, VIRTIO_BALLOON_F_STATS_VQ,
+    VIRTIO_BALLOON_S_MEMFREE, VIRTIO_BALLOON_S_MEMTOT,
+};
+use aero_virtio::devices::VirtioDevice;
+use aero_virtio::memory::{
+    read_u16_le, write_u16_le, write_u32_le, write_u64_le, GuestMemory, GuestRam,
+};
+use aero_virtio::pci::{
+    InterruptLog, VirtioPciDevice, VIRTIO_PCI_CAP_COMMON_CFG, VIRTIO_PCI_CAP_DEVICE_CFG,
+    VIRTIO_PCI_CAP_NOTIFY_CFG, VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER,
+    VIRTIO_STATUS_DRIVER_OK, VIRTIO_STATUS_FEATURES_OK,
+};
+
+#[derive(Default)]
+struct Caps {
+    common: u64,
+    notify: u64,
+    device: u64,
+    notify_mult: u32,
+}
+
+fn parse_caps(dev: &mut VirtioPciDevice) -> Caps {
+    let mut cfg = [0u8; 256];
+    dev.config_read(0, &mut cfg);
+    let mut caps = Caps::default();
+
+    let mut ptr = cfg[0x34] as usize;
+    while ptr != 0 {
+        let cap_id = cfg[ptr];
+        let next = cfg[ptr + 1] as usize;
+        if cap_id == 0x09 {
+            let cfg_type = cfg[ptr + 3];
+            let offset = u32::from_le_bytes(cfg[ptr + 8..ptr + 12].try_into().unwrap()) as u64;
+            match cfg_type {
+                VIRTIO_PCI_CAP_COMMON_CFG => caps.common = offset,
+                VIRTIO_PCI_CAP_NOTIFY_CFG => {
+                    caps.notify = offset;
+                    caps.notify_mult =
+                        u32::from_le_bytes(cfg[ptr + 16..ptr + 20].try_into().unwrap());
+                }
+                VIRTIO_PCI_CAP_DEVICE_CFG => caps.device = offset,
+                _ => {}
+            }
+        }
+        ptr = next;
+    }
+
+    caps
+}
+
+fn bar_read_u32(dev: &mut VirtioPciDevice, off: u64) -> u32 {
+    let mut buf = [0u8; 4];
+    dev.bar0_read(off, &mut buf);
+    u32::from_le_bytes(buf)
+}
+
+fn bar_read_u16(dev: &mut VirtioPciDevice, off: u64) -> u16 {
+    let mut buf = [0u8; 2];
+    dev.bar0_read(off, &mut buf);
+    u16::from_le_bytes(buf)
+}
+
+fn write_desc(
+    mem: &mut GuestRam,
+    table: u64,
+    index: u16,
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+) {
+    let base = table + u64::from(index) * 16;
+    write_u64_le(mem, base, addr).unwrap();
+    write_u32_le(mem, base + 8, len).unwrap();
+    write_u16_le(mem, base + 12, flags).unwrap();
+    write_u16_le(mem, base + 14, next).unwrap();
+}
+
+struct Queue {
+    desc: u64,
+    avail: u64,
+    used: u64,
+    notify: u64,
+}
+
+/// Negotiates all offered features, configures all three queues and sets DRIVER_OK.
+fn setup() -> (VirtioPciDevice, GuestRam, Caps, [Queue; 3]) {
+    let mut dev = VirtioPciDevice::new(
+        Box::new(VirtioBalloon::new()),
+        Box::new(InterruptLog::default()),
+    );
+    // Enable PCI memory decoding (BAR0 MMIO) + bus mastering (DMA).
+    dev.config_write(0x04, &0x0006u16.to_le_bytes());
+    let caps = parse_caps(&mut dev);
+    assert_ne!(caps.device, 0);
+
+    let mem = GuestRam::new(0x20000);
+
+    dev.bar0_write(caps.common + 0x14, &[VIRTIO_STATUS_ACKNOWLEDGE]);
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER],
+    );
+    for sel in 0u32..2 {
+        dev.bar0_write(caps.common, &sel.to_le_bytes());
+        let f = bar_read_u32(&mut dev, caps.common + 0x04);
+        if sel == 0 {
+            assert_ne!(u64::from(f) & VIRTIO_BALLOON_F_STATS_VQ, 0);
+        }
+        dev.bar0_write(caps.common + 0x08, &sel.to_le_bytes());
+        dev.bar0_write(caps.common + 0x0c, &f.to_le_bytes());
+    }
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_FEATURES_OK],
+    );
+
+    let queues = [0u16, 1, 2].map(|q| {
+        let base = 0x1000 + u64::from(q) * 0x3000;
+        let queue = Queue {
+            desc: base,
+            avail: base + 0x1000,
+            used: base + 0x2000,
+            notify: 0,
+        };
+        dev.bar0_write(caps.common + 0x16, &q.to_le_bytes());
+        dev.bar0_write(caps.common + 0x20, &queue.desc.to_le_bytes());
+        dev.bar0_write(caps.common + 0x28, &queue.avail.to_le_bytes());
+        dev.bar0_write(caps.common + 0x30, &queue.used.to_le_bytes());
+        dev.bar0_write(caps.common + 0x1c, &1u16.to_le_bytes());
+        let notify_off = bar_read_u16(&mut dev, caps.common + 0x1e);
+        Queue {
+            notify: caps.notify + u64::from(notify_off) * u64::from(caps.notify_mult),
+            ..queue
+        }
+    });
+
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE
+            | VIRTIO_STATUS_DRIVER
+            | VIRTIO_STATUS_FEATURES_OK
+            | VIRTIO_STATUS_DRIVER_OK],
+    );
+
+    (dev, mem, caps, queues)
+}
+
+/// Publishes descriptor `head` as avail ring entry `idx` and kicks the queue.
+fn submit(dev: &mut VirtioPciDevice, mem: &mut GuestRam, q: &Queue, idx: u16, head: u16) {
+    write_u16_le(mem, q.avail + 4 + u64::from(idx) * 2, head).unwrap();
+    write_u16_le(mem, q.avail + 2, idx + 1).unwrap();
+    dev.bar0_write(q.notify, &0u16.to_le_bytes());
+    dev.process_notified_queues(mem);
+}
+
+#[test]
+fn virtio_balloon_reports_inflate_and_deflate_pfns() {
+    let (mut dev, mut mem, caps, queues) = setup();
+
+    // Host sets a target; the driver reads it and reports progress via `actual`.
+    dev.device_mut::<VirtioBalloon>().unwrap().set_num_pages(3);
+    dev.signal_config_interrupt();
+    assert_eq!(bar_read_u32(&mut dev, caps.device), 3);
+
+    let pfns = 0x10000;
+    for (i, pfn) in [0x10u32, 0x11, 0x12].into_iter().enumerate() {
+        write_u32_le(&mut mem, pfns + i as u64 * 4, pfn).unwrap();
+    }
+    write_desc(&mut mem, queues[0].desc, 0, pfns, 12, 0, 0);
+    submit(&mut dev, &mut mem, &queues[0], 0, 0);
+    assert_eq!(read_u16_le(&mem, queues[0].used + 2).unwrap(), 1);
+
+    dev.bar0_write(caps.device + 4, &3u32.to_le_bytes());
+    // `num_pages` is read-only for the driver.
+    dev.bar0_write(caps.device, &0u32.to_le_bytes());
+
+    write_desc(&mut mem, queues[1].desc, 0, pfns + 4, 4, 0, 0);
+    submit(&mut dev, &mut mem, &queues[1], 0, 0);
+    assert_eq!(read_u16_le(&mem, queues[1].used + 2).unwrap(), 1);
+
+    let balloon = dev.device_mut::<VirtioBalloon>().unwrap();
+    assert_eq!(balloon.num_pages(), 3);
+    assert_eq!(balloon.actual(), 3);
+    assert_eq!(
+        balloon.take_events(),
+        vec![
+            VirtioBalloonEvent::Inflate(vec![0x10, 0x11, 0x12]),
+            VirtioBalloonEvent::Deflate(vec![0x11]),
+        ]
+    );
+    assert!(balloon.take_events().is_empty());
+
+    // The host target survives a device reset; the guest's progress does not.
+    dev.bar0_write(caps.common + 0x14, &[0]);
+    let balloon = dev.device_mut::<VirtioBalloon>().unwrap();
+    assert_eq!(balloon.num_pages(), 3);
+    assert_eq!(balloon.actual(), 0);
+}
+
+#[test]
+fn virtio_balloon_holds_stats_buffer_until_requested() {
+    let (mut dev, mut mem, _caps, queues) = setup();
+    let q = &queues[2];
+
+    let write_stats = |mem: &mut GuestRam, free: u64| {
+        let mut buf = Vec::new();
+        for (tag, val) in [
+            (VIRTIO_BALLOON_S_MEMFREE, free),
+            (VIRTIO_BALLOON_S_MEMTOT, 0x100_0000),
+            (0xffff, 1),
+        ] {
+            buf.extend_from_slice(&tag.to_le_bytes());
+            buf.extend_from_slice(&val.to_le_bytes());
+        }
+        mem.write(0x10000, &buf).unwrap();
+    };
+
+    write_stats(&mut mem, 0x1000);
+    write_desc(&mut mem, q.desc, 0, 0x10000, 30, 0, 0);
+    submit(&mut dev, &mut mem, q, 0, 0);
+
+    // The first buffer is parsed but held by the device.
+    assert_eq!(read_u16_le(&mem, q.used + 2).unwrap(), 0);
+    let expected = VirtioBalloonStats {
+        free_memory: Some(0x1000),
+        total_memory: Some(0x100_0000),
+        ..Default::default()
+    };
+    assert_eq!(
+        dev.device::<VirtioBalloon>().unwrap().stats(),
+        Some(expected)
+    );
+
+    // Polling without a request keeps holding it.
+    dev.poll(&mut mem);
+    assert_eq!(read_u16_le(&mem, q.used + 2).unwrap(), 0);
+
+    dev.device_mut::<VirtioBalloon>().unwrap().request_stats();
+    dev.poll(&mut mem);
+    assert_eq!(read_u16_le(&mem, q.used + 2).unwrap(), 1);
+
+    // The driver refills and re-posts the buffer.
+    write_stats(&mut mem, 0x2000);
+    submit(&mut dev, &mut mem, q, 1, 0);
+    assert_eq!(read_u16_le(&mem, q.used + 2).unwrap(), 1);
+    assert_eq!(
+        dev.device::<VirtioBalloon>()
+            .unwrap()
+            .stats()
+            .unwrap()
+            .free_memory,
+        Some(0x2000)
+    );
+}
+
+#[test]
+fn virtio_balloon_device_state_roundtrips() {
+    let mut balloon = VirtioBalloon::new();
+    balloon.set_num_pages(0x1234);
+    balloon.write_config(4, &7u32.to_le_bytes());
+    let state = balloon.snapshot_device_state().unwrap();
+
+    let mut restored = VirtioBalloon::new();
+    restored.restore_device_state(&state);
+    assert_eq!(restored.num_pages(), 0x1234);
+    assert_eq!(restored.actual(), 7);
+}
@@ -2,13 +2,14 @@ use aero_io_snapshot::io::state::IoSnapshot;
 use aero_platform::interrupts::msi::MsiMessage;
 use aero_storage::{
     AeroSparseConfig, AeroSparseDisk, DiskError as StorageDiskError, MemBackend, RawDisk,
-    VirtualDisk,
+    StorageFaultDelay, StorageFaultInjector, StorageFaultPolicy, VirtualDisk,
 };
 use aero_virtio::devices::blk::{
-    VirtioBlk, VIRTIO_BLK_MAX_REQUEST_DATA_BYTES, VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS,
-    VIRTIO_BLK_SECTOR_SIZE, VIRTIO_BLK_S_IOERR, VIRTIO_BLK_S_UNSUPP, VIRTIO_BLK_T_DISCARD,
-    VIRTIO_BLK_T_FLUSH, VIRTIO_BLK_T_GET_ID, VIRTIO_BLK_T_IN, VIRTIO_BLK_T_OUT,
-    VIRTIO_BLK_T_WRITE_ZEROES, VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP,
+    VirtioBlk, VirtioBlkOptions, VIRTIO_BLK_MAX_REQUEST_DATA_BYTES,
+    VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS, VIRTIO_BLK_SECTOR_SIZE, VIRTIO_BLK_S_IOERR,
+    VIRTIO_BLK_S_UNSUPP, VIRTIO_BLK_T_DISCARD, VIRTIO_BLK_T_FLUSH, VIRTIO_BLK_T_GET_ID,
+    VIRTIO_BLK_T_IN, VIRTIO_BLK_T_OUT, VIRTIO_BLK_T_WRITE_ZEROES,
+    VIRTIO_BLK_WRITE_ZEROES_FLAG_UNMAP,
 };
 use aero_virtio::memory::{
     read_u32_le, write_u16_le, write_u32_le, write_u64_le, GuestMemory, GuestRam,
@@ -521,6 +522,7 @@ fn virtio_blk_config_read_out_of_range_offsets_return_zeroes() {
         size_max: 0,
         seg_max: 126,
         blk_size: SECTOR_SIZE_U32,
+        num_queues: 1,
     };
 
     let mut buf = [0xa5u8; 16];
@@ -536,6 +538,7 @@ fn virtio_blk_config_read_does_not_truncate_large_offsets_on_32bit() {
         size_max: 0,
         seg_max: 126,
         blk_size: SECTOR_SIZE_U32,
+        num_queues: 1,
     };
 
     // On 32-bit targets, `u64 as usize` truncates. Ensure out-of-range offsets never alias within
@@ -862,6 +865,7 @@ fn virtio_blk_discard_reclaims_sparse_blocks_and_reads_zero() {
         AeroSparseConfig {
             disk_size_bytes: capacity_bytes,
             block_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -1220,6 +1224,174 @@ fn virtio_blk_flush_calls_backend_flush() {
     assert_eq!(read_u32_le(&mem, USED_RING + 8).unwrap(), 0);
 }
 
+/// `SharedDisk` with ordered writes: `write_barrier` is counted instead of falling back to a flush.
+struct OrderedSharedDisk {
+    inner: SharedDisk,
+    barriers: Arc<AtomicU32>,
+}
+
+impl VirtualDisk for OrderedSharedDisk {
+    fn capacity_bytes(&self) -> u64 {
+        self.inner.capacity_bytes()
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> aero_storage::Result<()> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> aero_storage::Result<()> {
+        self.inner.write_at(offset, buf)
+    }
+
+    fn flush(&mut self) -> aero_storage::Result<()> {
+        self.inner.flush()
+    }
+
+    fn write_barrier(&mut self) -> aero_storage::Result<()> {
+        self.barriers.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn virtio_blk_write_flush_write_flush_batch_costs_one_backend_flush() {
+    let backing = Arc::new(Mutex::new(vec![0u8; 4096]));
+    let flushes = Arc::new(AtomicU32::new(0));
+    let barriers = Arc::new(AtomicU32::new(0));
+    let backend = OrderedSharedDisk {
+        inner: SharedDisk {
+            data: backing.clone(),
+            flushes: flushes.clone(),
+        },
+        barriers: barriers.clone(),
+    };
+    let blk = VirtioBlk::new(Box::new(backend));
+    let dev = VirtioPciDevice::new(Box::new(blk), Box::new(InterruptLog::default()));
+    let (mut dev, caps, mut mem) = setup_pci_device(dev);
+
+    // Four chains in one kick: OUT sector 0, FLUSH, OUT sector 1, FLUSH.
+    let headers = [0x7000u64, 0x7100, 0x7200, 0x7300];
+    let data = [0x8000u64, 0x8400];
+    let statuses = [0x9000u64, 0x9001, 0x9002, 0x9003];
+    for (i, (&header, typ)) in headers
+        .iter()
+        .zip([
+            VIRTIO_BLK_T_OUT,
+            VIRTIO_BLK_T_FLUSH,
+            VIRTIO_BLK_T_OUT,
+            VIRTIO_BLK_T_FLUSH,
+        ])
+        .enumerate()
+    {
+        write_u32_le(&mut mem, header, typ).unwrap();
+        write_u32_le(&mut mem, header + 4, 0).unwrap();
+        write_u64_le(&mut mem, header + 8, (i / 2) as u64).unwrap();
+    }
+    mem.write(data[0], &[0xaa; 512]).unwrap();
+    mem.write(data[1], &[0xbb; 512]).unwrap();
+    for status in statuses {
+        mem.write(status, &[0xff]).unwrap();
+    }
+
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        0,
+        headers[0],
+        16,
+        VIRTQ_DESC_F_NEXT,
+        1,
+    );
+    write_desc(&mut mem, DESC_TABLE, 1, data[0], 512, VIRTQ_DESC_F_NEXT, 2);
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        2,
+        statuses[0],
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        3,
+        headers[1],
+        16,
+        VIRTQ_DESC_F_NEXT,
+        4,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        4,
+        statuses[1],
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        5,
+        headers[2],
+        16,
+        VIRTQ_DESC_F_NEXT,
+        6,
+    );
+    write_desc(&mut mem, DESC_TABLE, 6, data[1], 512, VIRTQ_DESC_F_NEXT, 7);
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        7,
+        statuses[2],
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        8,
+        headers[3],
+        16,
+        VIRTQ_DESC_F_NEXT,
+        9,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        9,
+        statuses[3],
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+
+    write_u16_le(&mut mem, AVAIL_RING, 0).unwrap();
+    for (slot, head) in [0u16, 3, 5, 8].into_iter().enumerate() {
+        write_u16_le(&mut mem, AVAIL_RING + 4 + slot as u64 * 2, head).unwrap();
+    }
+    write_u16_le(&mut mem, AVAIL_RING + 2, 4).unwrap();
+    write_u16_le(&mut mem, USED_RING, 0).unwrap();
+    write_u16_le(&mut mem, USED_RING + 2, 0).unwrap();
+
+    kick_queue0(&mut dev, &caps, &mut mem);
+
+    for status in statuses {
+        assert_eq!(mem.get_slice(status, 1).unwrap()[0], 0);
+    }
+    assert_eq!(dev.debug_queue_used_idx(&mem, 0), Some(4));
+    // The first FLUSH is demoted to an ordering barrier ahead of the second write; the
+    // second one is satisfied by the single durability flush at the end of the batch.
+    assert_eq!(barriers.load(Ordering::SeqCst), 1);
+    assert_eq!(flushes.load(Ordering::SeqCst), 1);
+
+    let backing = backing.lock().unwrap();
+    assert!(backing[..512].iter().all(|b| *b == 0xaa));
+    assert!(backing[512..1024].iter().all(|b| *b == 0xbb));
+}
+
 #[test]
 fn virtio_blk_msix_queue_interrupts_use_programmed_msix_message_and_do_not_fallback_to_intx() {
     let irq0 = TestIrq::default();
@@ -2751,3 +2923,363 @@ fn virtio_blk_rejects_sector_offset_overflow() {
     assert_eq!(dev.debug_queue_used_idx(&mem, 0), Some(1));
     assert!(backing.lock().unwrap().iter().all(|b| *b == 0));
 }
+
+fn setup_with_options(
+    options: VirtioBlkOptions,
+    disk_len: usize,
+    mem_len: usize,
+    irq: TestIrq,
+) -> (VirtioPciDevice, Caps, GuestRam, Arc<Mutex<Vec<u8>>>) {
+    let backing = Arc::new(Mutex::new(vec![0u8; disk_len]));
+    let backend = SharedDisk {
+        data: backing.clone(),
+        flushes: Arc::new(AtomicU32::new(0)),
+    };
+
+    let blk = VirtioBlk::with_options(Box::new(backend), options);
+    let dev = VirtioPciDevice::new(Box::new(blk), Box::new(irq));
+
+    let (dev, caps, _mem) = setup_pci_device(dev);
+    (dev, caps, GuestRam::new(mem_len), backing)
+}
+
+fn used_ring_heads(mem: &GuestRam, used_ring: u64) -> Vec<u32> {
+    let used_idx = u16::from_le_bytes(mem.get_slice(used_ring + 2, 2).unwrap().try_into().unwrap());
+    (0..u64::from(used_idx))
+        .map(|i| read_u32_le(mem, used_ring + 4 + i * 8).unwrap())
+        .collect()
+}
+
+#[test]
+fn virtio_blk_reads_1mib_through_indirect_table_and_completes_in_order() {
+    const PAGE: usize = 4096;
+    const PAGES: usize = 256;
+    let (mut dev, caps, mut mem, backing) = setup_with_options(
+        VirtioBlkOptions {
+            backend_limits: true,
+            ..Default::default()
+        },
+        2 * 1024 * 1024,
+        0x30_0000,
+        TestIrq::default(),
+    );
+
+    // Backend limits: SIZE_MAX is offered and both limits cover a 1MiB single-table request.
+    bar_write_u32(&mut dev, caps.common, 0);
+    assert_ne!(bar_read_u32(&mut dev, caps.common + 0x04) & (1 << 1), 0);
+    assert_eq!(
+        u64::from(bar_read_u32(&mut dev, caps.device + 8)),
+        VIRTIO_BLK_MAX_REQUEST_DATA_BYTES
+    );
+    assert_eq!(
+        bar_read_u32(&mut dev, caps.device + 12) as usize,
+        VIRTIO_BLK_MAX_REQUEST_DESCRIPTORS - 2
+    );
+
+    let pattern: Vec<u8> = (0..backing.lock().unwrap().len())
+        .map(|i| (i / SECTOR_SIZE_BYTES) as u8 ^ (i as u8).wrapping_mul(7))
+        .collect();
+    backing.lock().unwrap().copy_from_slice(&pattern);
+
+    // Three levels: ring descriptor (header) -> ring descriptor (INDIRECT) -> indirect table with
+    // 256 x 4KiB data buffers (scattered in reverse order) + status.
+    let header = 0x7000;
+    let status = 0x9000;
+    let table = 0x1_0000;
+    let data_base = 0x10_0000;
+    let sector = 1024u64;
+    write_u32_le(&mut mem, header, VIRTIO_BLK_T_IN).unwrap();
+    write_u32_le(&mut mem, header + 4, 0).unwrap();
+    write_u64_le(&mut mem, header + 8, sector).unwrap();
+    mem.write(status, &[0xff]).unwrap();
+
+    let buf_addr = |i: usize| data_base + ((PAGES - 1 - i) * PAGE) as u64;
+    for i in 0..PAGES {
+        write_desc(
+            &mut mem,
+            table,
+            i as u16,
+            buf_addr(i),
+            PAGE as u32,
+            VIRTQ_DESC_F_WRITE | VIRTQ_DESC_F_NEXT,
+            (i + 1) as u16,
+        );
+    }
+    write_desc(
+        &mut mem,
+        table,
+        PAGES as u16,
+        status,
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+    write_desc(&mut mem, DESC_TABLE, 0, header, 16, VIRTQ_DESC_F_NEXT, 1);
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        1,
+        table,
+        ((PAGES + 1) * 16) as u32,
+        VIRTQ_DESC_F_INDIRECT,
+        0,
+    );
+
+    // A FLUSH posted behind the read completes after it.
+    let flush_header = 0x7100;
+    let flush_status = 0x9100;
+    write_u32_le(&mut mem, flush_header, VIRTIO_BLK_T_FLUSH).unwrap();
+    write_u64_le(&mut mem, flush_header + 8, 0).unwrap();
+    mem.write(flush_status, &[0xff]).unwrap();
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        2,
+        flush_header,
+        16,
+        VIRTQ_DESC_F_NEXT,
+        3,
+    );
+    write_desc(
+        &mut mem,
+        DESC_TABLE,
+        3,
+        flush_status,
+        1,
+        VIRTQ_DESC_F_WRITE,
+        0,
+    );
+
+    write_u16_le(&mut mem, AVAIL_RING, 0).unwrap();
+    write_u16_le(&mut mem, AVAIL_RING + 4, 0).unwrap();
+    write_u16_le(&mut mem, AVAIL_RING + 6, 2).unwrap();
+    write_u16_le(&mut mem, AVAIL_RING + 2, 2).unwrap();
+    write_u16_le(&mut mem, USED_RING, 0).unwrap();
+    write_u16_le(&mut mem, USED_RING + 2, 0).unwrap();
+
+    kick_queue0(&mut dev, &caps, &mut mem);
+
+    assert_eq!(mem.get_slice(status, 1).unwrap()[0], 0);
+    assert_eq!(mem.get_slice(flush_status, 1).unwrap()[0], 0);
+    let disk_off = (sector * VIRTIO_BLK_SECTOR_SIZE) as usize;
+    for i in 0..PAGES {
+        let expected = &pattern[disk_off + i * PAGE..disk_off + (i + 1) * PAGE];
+        assert_eq!(
+            mem.get_slice(buf_addr(i), PAGE).unwrap(),
+            expected,
+            "page {i}"
+        );
+    }
+    assert_eq!(used_ring_heads(&mem, USED_RING), [0, 2]);
+    // Contract v1: used.len MUST be 0 for all virtio-blk completions.
+    assert_eq!(read_u32_le(&mem, USED_RING + 8).unwrap(), 0);
+    assert_eq!(read_u32_le(&mem, USED_RING + 16).unwrap(), 0);
+}
+
+#[test]
+fn virtio_blk_multi_queue_routes_completions_to_per_queue_msix_vectors() {
+    let irq = TestIrq::default();
+    let (mut dev, caps, mut mem, _backing) = setup_with_options(
+        VirtioBlkOptions {
+            num_queues: 4,
+            ..Default::default()
+        },
+        4096,
+        0x10000,
+        irq.clone(),
+    );
+
+    bar_write_u32(&mut dev, caps.common, 0);
+    assert_ne!(bar_read_u32(&mut dev, caps.common + 0x04) & (1 << 12), 0);
+    assert_eq!(bar_read_u16(&mut dev, caps.common + 0x12), 4);
+    assert_eq!(bar_read_u16(&mut dev, caps.device + 34), 4);
+
+    // MSI-X: one vector per queue plus the config vector.
+    let mut cfg = [0u8; 256];
+    dev.config_read(0, &mut cfg);
+    let mut ptr = cfg[0x34] as usize;
+    while cfg[ptr] != 0x11 {
+        ptr = cfg[ptr + 1] as usize;
+        assert_ne!(ptr, 0, "missing MSI-X capability");
+    }
+    let msg_ctl = u16::from_le_bytes([cfg[ptr + 2], cfg[ptr + 3]]);
+    assert_eq!(msg_ctl & 0x7ff, 4);
+    dev.config_write(ptr as u16 + 0x02, &(msg_ctl | (1 << 15)).to_le_bytes());
+    let table_offset =
+        u64::from(u32::from_le_bytes(cfg[ptr + 4..ptr + 8].try_into().unwrap()) & !0x7);
+    for vector in 0..5u64 {
+        let entry = table_offset + vector * 16;
+        bar_write_u32(&mut dev, entry, 0xfee0_0000);
+        bar_write_u32(&mut dev, entry + 0x04, 0);
+        bar_write_u32(&mut dev, entry + 0x08, 0x0040 + vector as u32);
+        bar_write_u32(&mut dev, entry + 0x0c, 0);
+    }
+
+    // Queue 2 gets its own rings and vector 3.
+    let (desc, avail, used) = (0xA000u64, 0xB000u64, 0xC000u64);
+    bar_write_u16(&mut dev, caps.common + 0x16, 2);
+    bar_write_u64(&mut dev, caps.common + 0x20, desc);
+    bar_write_u64(&mut dev, caps.common + 0x28, avail);
+    bar_write_u64(&mut dev, caps.common + 0x30, used);
+    bar_write_u16(&mut dev, caps.common + 0x1a, 3);
+    bar_write_u16(&mut dev, caps.common + 0x1c, 1);
+    let notify_off = bar_read_u16(&mut dev, caps.common + 0x1e);
+
+    let header = 0x7000;
+    let status = 0x9000;
+    write_u32_le(&mut mem, header, VIRTIO_BLK_T_FLUSH).unwrap();
+    write_u64_le(&mut mem, header + 8, 0).unwrap();
+    mem.write(status, &[0xff]).unwrap();
+    write_desc(&mut mem, desc, 0, header, 16, VIRTQ_DESC_F_NEXT, 1);
+    write_desc(&mut mem, desc, 1, status, 1, VIRTQ_DESC_F_WRITE, 0);
+    write_u16_le(&mut mem, avail + 4, 0).unwrap();
+    write_u16_le(&mut mem, avail + 2, 1).unwrap();
+
+    dev.bar0_write(
+        caps.notify + u64::from(notify_off) * u64::from(caps.notify_mult),
+        &2u16.to_le_bytes(),
+    );
+    dev.process_notified_queues(&mut mem);
+
+    assert_eq!(mem.get_slice(status, 1).unwrap()[0], 0);
+    assert_eq!(used_ring_heads(&mem, used), [0]);
+    assert_eq!(dev.debug_queue_used_idx(&mem, 0), Some(0));
+    assert_eq!(
+        irq.take_msix_messages(),
+        vec![MsiMessage {
+            address: 0xfee0_0000,
+            data: 0x0043,
+        }]
+    );
+}
+
+#[test]
+fn virtio_blk_injected_latency_completes_requests_out_of_order() {
+    let (mut dev, caps, mut mem, _backing) = setup_with_options(
+        VirtioBlkOptions::default(),
+        4096,
+        0x10000,
+        TestIrq::default(),
+    );
+    let injector = |ns| {
+        Some(StorageFaultInjector::new(StorageFaultPolicy {
+            delay: StorageFaultDelay::Fixed { ns },
+            ..Default::default()
+        }))
+    };
+
+    let post_flush = |mem: &mut GuestRam, head: u16, slot: u16| {
+        let header = 0x7000 + u64::from(head) * 0x100;
+        let status = 0x9000 + u64::from(head) * 0x100;
+        write_u32_le(mem, header, VIRTIO_BLK_T_FLUSH).unwrap();
+        write_u64_le(mem, header + 8, 0).unwrap();
+        mem.write(status, &[0xff]).unwrap();
+        write_desc(
+            mem,
+            DESC_TABLE,
+            head,
+            header,
+            16,
+            VIRTQ_DESC_F_NEXT,
+            head + 1,
+        );
+        write_desc(mem, DESC_TABLE, head + 1, status, 1, VIRTQ_DESC_F_WRITE, 0);
+        write_u16_le(mem, AVAIL_RING + 4 + u64::from(slot) * 2, head).unwrap();
+        write_u16_le(mem, AVAIL_RING + 2, slot + 1).unwrap();
+    };
+    write_u16_le(&mut mem, USED_RING + 2, 0).unwrap();
+
+    // Head 0 is slow, head 2 (submitted later) is fast.
+    let blk = dev.device_mut::<VirtioBlk>().unwrap();
+    blk.set_time_ns(0);
+    blk.set_fault_injector(injector(1_000));
+    post_flush(&mut mem, 0, 0);
+    kick_queue0(&mut dev, &caps, &mut mem);
+    dev.device_mut::<VirtioBlk>()
+        .unwrap()
+        .set_fault_injector(injector(100));
+    post_flush(&mut mem, 2, 1);
+    kick_queue0(&mut dev, &caps, &mut mem);
+    assert!(used_ring_heads(&mem, USED_RING).is_empty());
+
+    // Due completions are published without a new notify.
+    dev.device_mut::<VirtioBlk>().unwrap().set_time_ns(100);
+    dev.process_notified_queues(&mut mem);
+    assert_eq!(used_ring_heads(&mem, USED_RING), [2]);
+
+    dev.device_mut::<VirtioBlk>().unwrap().set_time_ns(1_000);
+    dev.process_notified_queues(&mut mem);
+    assert_eq!(used_ring_heads(&mem, USED_RING), [2, 0]);
+    assert_eq!(mem.get_slice(0x9000, 1).unwrap()[0], 0);
+    assert_eq!(mem.get_slice(0x9200, 1).unwrap()[0], 0);
+}
+
+#[test]
+fn virtio_blk_snapshot_restore_preserves_delayed_completions() {
+    let (mut dev, caps, mut mem, _backing) = setup_with_options(
+        VirtioBlkOptions::default(),
+        4096,
+        0x10000,
+        TestIrq::default(),
+    );
+    let injector = || {
+        Some(StorageFaultInjector::new(StorageFaultPolicy {
+            delay: StorageFaultDelay::Fixed { ns: 1_000 },
+            ..Default::default()
+        }))
+    };
+
+    let header = 0x7000;
+    let status = 0x9000;
+    write_u32_le(&mut mem, header, VIRTIO_BLK_T_FLUSH).unwrap();
+    write_u64_le(&mut mem, header + 8, 0).unwrap();
+    mem.write(status, &[0xff]).unwrap();
+    write_desc(&mut mem, DESC_TABLE, 0, header, 16, VIRTQ_DESC_F_NEXT, 1);
+    write_desc(&mut mem, DESC_TABLE, 1, status, 1, VIRTQ_DESC_F_WRITE, 0);
+    write_u16_le(&mut mem, AVAIL_RING + 4, 0).unwrap();
+    write_u16_le(&mut mem, AVAIL_RING + 2, 1).unwrap();
+    write_u16_le(&mut mem, USED_RING + 2, 0).unwrap();
+
+    let blk = dev.device_mut::<VirtioBlk>().unwrap();
+    blk.set_time_ns(0);
+    blk.set_fault_injector(injector());
+    kick_queue0(&mut dev, &caps, &mut mem);
+    assert!(used_ring_heads(&mem, USED_RING).is_empty());
+
+    let snap_bytes = dev.save_state();
+
+    // With the injector re-attached, the restored request still waits for its due time.
+    let (mut restored, _caps, _mem, _backing) = setup_with_options(
+        VirtioBlkOptions::default(),
+        4096,
+        0x10000,
+        TestIrq::default(),
+    );
+    restored.load_state(&snap_bytes).unwrap();
+    let mut mem2 = mem.clone();
+    let blk = restored.device_mut::<VirtioBlk>().unwrap();
+    blk.set_fault_injector(injector());
+    blk.set_time_ns(999);
+    restored.process_notified_queues(&mut mem2);
+    assert!(used_ring_heads(&mem2, USED_RING).is_empty());
+
+    restored
+        .device_mut::<VirtioBlk>()
+        .unwrap()
+        .set_time_ns(1_000);
+    restored.process_notified_queues(&mut mem2);
+    assert_eq!(used_ring_heads(&mem2, USED_RING), [0]);
+    assert_eq!(mem2.get_slice(status, 1).unwrap()[0], 0);
+
+    // Without an injector, the restored request is released on the next poll.
+    let (mut restored, _caps, _mem, _backing) = setup_with_options(
+        VirtioBlkOptions::default(),
+        4096,
+        0x10000,
+        TestIrq::default(),
+    );
+    restored.load_state(&snap_bytes).unwrap();
+    let mut mem3 = mem.clone();
+    restored.process_notified_queues(&mut mem3);
+    assert_eq!(used_ring_heads(&mem3, USED_RING), [0]);
+}
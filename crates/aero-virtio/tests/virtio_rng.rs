@@ -0,0 +1,239 @@
+use aero_virtio::devices::rng::{
+    ChaChaEntropySource, EntropySource, VirtioRng, VIRTIO_RNG_DEFAULT_BYTES_PER_TICK,
+};
+use aero_virtio::devices::VirtioDevice;
+use aero_virtio::memory::{
+    read_u16_le, read_u32_le, write_u16_le, write_u32_le, write_u64_le, GuestMemory, GuestRam,
+};
+use aero_virtio::pci::{
+    InterruptLog, VirtioPciDevice, VIRTIO_PCI_CAP_COMMON_CFG, VIRTIO_PCI_CAP_NOTIFY_CFG,
+    VIRTIO_STATUS_ACKNOWLEDGE, VIRTIO_STATUS_DRIVER, VIRTIO_STATUS_DRIVER_OK,
+    VIRTIO_STATUS_FEATURES_OK,
+};
+use aero_virtio::queue::VIRTQ_DESC_F_WRITE;
+
+const DESC: u64 = 0x1000;
+const AVAIL: u64 = 0x2000;
+const USED: u64 = 0x3000;
+const BUFS: u64 = 0x10000;
+
+#[derive(Default)]
+struct Caps {
+    common: u64,
+    notify: u64,
+    notify_mult: u32,
+}
+
+fn parse_caps(dev: &mut VirtioPciDevice) -> Caps {
+    let mut cfg = [0u8; 256];
+    dev.config_read(0, &mut cfg);
+    let mut caps = Caps::default();
+
+    let mut ptr = cfg[0x34] as usize;
+    while ptr != 0 {
+        let cap_id = cfg[ptr];
+        let next = cfg[ptr + 1] as usize;
+        if cap_id == 0x09 {
+            let cfg_type = cfg[ptr + 3];
+            let offset = u32::from_le_bytes(cfg[ptr + 8..ptr + 12].try_into().unwrap()) as u64;
+            match cfg_type {
+                VIRTIO_PCI_CAP_COMMON_CFG => caps.common = offset,
+                VIRTIO_PCI_CAP_NOTIFY_CFG => {
+                    caps.notify = offset;
+                    caps.notify_mult =
+                        u32::from_le_bytes(cfg[ptr + 16..ptr + 20].try_into().unwrap());
+                }
+                _ => {}
+            }
+        }
+        ptr = next;
+    }
+
+    caps
+}
+
+fn bar_read_u32(dev: &mut VirtioPciDevice, off: u64) -> u32 {
+    let mut buf = [0u8; 4];
+    dev.bar0_read(off, &mut buf);
+    u32::from_le_bytes(buf)
+}
+
+fn bar_read_u16(dev: &mut VirtioPciDevice, off: u64) -> u16 {
+    let mut buf = [0u8; 2];
+    dev.bar0_read(off, &mut buf);
+    u16::from_le_bytes(buf)
+}
+
+/// Negotiates all offered features, configures the request queue and sets DRIVER_OK. Returns the
+/// queue's notify address.
+fn setup(rng: VirtioRng) -> (VirtioPciDevice, GuestRam, u64) {
+    let mut dev = VirtioPciDevice::new(Box::new(rng), Box::new(InterruptLog::default()));
+    // Enable PCI memory decoding (BAR0 MMIO) + bus mastering (DMA).
+    dev.config_write(0x04, &0x0006u16.to_le_bytes());
+    let caps = parse_caps(&mut dev);
+
+    let mem = GuestRam::new(0x20000);
+
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER],
+    );
+    for sel in 0u32..2 {
+        dev.bar0_write(caps.common, &sel.to_le_bytes());
+        let f = bar_read_u32(&mut dev, caps.common + 0x04);
+        dev.bar0_write(caps.common + 0x08, &sel.to_le_bytes());
+        dev.bar0_write(caps.common + 0x0c, &f.to_le_bytes());
+    }
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_FEATURES_OK],
+    );
+
+    dev.bar0_write(caps.common + 0x16, &0u16.to_le_bytes());
+    dev.bar0_write(caps.common + 0x20, &DESC.to_le_bytes());
+    dev.bar0_write(caps.common + 0x28, &AVAIL.to_le_bytes());
+    dev.bar0_write(caps.common + 0x30, &USED.to_le_bytes());
+    dev.bar0_write(caps.common + 0x1c, &1u16.to_le_bytes());
+    let notify_off = bar_read_u16(&mut dev, caps.common + 0x1e);
+    let notify = caps.notify + u64::from(notify_off) * u64::from(caps.notify_mult);
+
+    dev.bar0_write(
+        caps.common + 0x14,
+        &[VIRTIO_STATUS_ACKNOWLEDGE
+            | VIRTIO_STATUS_DRIVER
+            | VIRTIO_STATUS_FEATURES_OK
+            | VIRTIO_STATUS_DRIVER_OK],
+    );
+
+    (dev, mem, notify)
+}
+
+/// Posts `lens.len()` single-descriptor write-only buffers of the given sizes at `BUFS + i * 0x1000`
+/// and kicks the queue.
+fn post_buffers(dev: &mut VirtioPciDevice, mem: &mut GuestRam, notify: u64, lens: &[u32]) {
+    let start = read_u16_le(mem, AVAIL + 2).unwrap();
+    for (i, &len) in lens.iter().enumerate() {
+        let idx = start + i as u16;
+        let base = DESC + u64::from(idx) * 16;
+        write_u64_le(mem, base, BUFS + u64::from(idx) * 0x1000).unwrap();
+        write_u32_le(mem, base + 8, len).unwrap();
+        write_u16_le(mem, base + 12, VIRTQ_DESC_F_WRITE).unwrap();
+        write_u16_le(mem, base + 14, 0).unwrap();
+        write_u16_le(mem, AVAIL + 4 + u64::from(idx) * 2, idx).unwrap();
+    }
+    write_u16_le(mem, AVAIL + 2, start + lens.len() as u16).unwrap();
+    dev.bar0_write(notify, &0u16.to_le_bytes());
+    dev.process_notified_queues(mem);
+}
+
+/// `(head, len)` of used ring entry `i`.
+fn used_entry(mem: &GuestRam, i: u16) -> (u32, u32) {
+    let base = USED + 4 + u64::from(i) * 8;
+    (
+        read_u32_le(mem, base).unwrap(),
+        read_u32_le(mem, base + 4).unwrap(),
+    )
+}
+
+fn buffer(mem: &GuestRam, idx: u16, len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    mem.read(BUFS + u64::from(idx) * 0x1000, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn virtio_rng_fills_buffers_from_the_seeded_stream() {
+    let (mut dev, mut mem, notify) = setup(VirtioRng::new(42));
+    post_buffers(&mut dev, &mut mem, notify, &[16, 100]);
+
+    assert_eq!(read_u16_le(&mem, USED + 2).unwrap(), 2);
+    assert_eq!(used_entry(&mem, 0), (0, 16));
+    assert_eq!(used_entry(&mem, 1), (1, 100));
+
+    let mut expected = [0u8; 116];
+    ChaChaEntropySource::new(42).fill_bytes(&mut expected);
+    assert_eq!(buffer(&mem, 0, 16), expected[..16]);
+    assert_eq!(buffer(&mem, 1, 100), expected[16..]);
+    assert_eq!(
+        dev.device::<VirtioRng>()
+            .unwrap()
+            .deterministic_source()
+            .position(),
+        116
+    );
+}
+
+#[test]
+fn virtio_rng_bounds_bytes_per_tick() {
+    let (mut dev, mut mem, notify) = setup(VirtioRng::new(1));
+    let budget = VIRTIO_RNG_DEFAULT_BYTES_PER_TICK as u32;
+
+    // Three buffers of 3/4 of the budget: the first fits, the second is filled partially and the
+    // third waits for the next tick.
+    let len = budget / 4 * 3;
+    post_buffers(&mut dev, &mut mem, notify, &[len, len, len]);
+    assert_eq!(read_u16_le(&mem, USED + 2).unwrap(), 2);
+    assert_eq!(used_entry(&mem, 0), (0, len));
+    assert_eq!(used_entry(&mem, 1), (1, budget - len));
+    assert_eq!(dev.device::<VirtioRng>().unwrap().pending_buffers(), 1);
+
+    // Polling without a new tick does nothing.
+    dev.poll(&mut mem);
+    assert_eq!(read_u16_le(&mem, USED + 2).unwrap(), 2);
+
+    dev.device_mut::<VirtioRng>().unwrap().refill_budget();
+    dev.poll(&mut mem);
+    assert_eq!(read_u16_le(&mem, USED + 2).unwrap(), 3);
+    assert_eq!(used_entry(&mem, 2), (2, len));
+    assert_eq!(dev.device::<VirtioRng>().unwrap().pending_buffers(), 0);
+}
+
+struct CountingSource(u8);
+
+impl EntropySource for CountingSource {
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            *b = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+}
+
+#[test]
+fn virtio_rng_host_source_replaces_the_deterministic_stream() {
+    let mut rng = VirtioRng::new(7);
+    rng.set_host_source(Some(Box::new(CountingSource(0))));
+    let (mut dev, mut mem, notify) = setup(rng);
+
+    post_buffers(&mut dev, &mut mem, notify, &[4]);
+    assert_eq!(buffer(&mem, 0, 4), [0, 1, 2, 3]);
+
+    let rng = dev.device::<VirtioRng>().unwrap();
+    assert!(rng.has_host_source());
+    // The deterministic stream was not consumed.
+    assert_eq!(rng.deterministic_source().position(), 0);
+    assert_eq!(rng.snapshot_device_state().unwrap()[1], 1);
+}
+
+#[test]
+fn virtio_rng_device_state_resumes_the_stream() {
+    let mut rng = VirtioRng::new(0x1234_5678_9abc_def0);
+    let (mut dev, mut mem, notify) = setup(VirtioRng::new(0x1234_5678_9abc_def0));
+    post_buffers(&mut dev, &mut mem, notify, &[37]);
+    let state = dev
+        .device::<VirtioRng>()
+        .unwrap()
+        .snapshot_device_state()
+        .unwrap();
+
+    rng.restore_device_state(&state);
+    assert_eq!(rng.deterministic_source().seed(), 0x1234_5678_9abc_def0);
+    assert_eq!(rng.deterministic_source().position(), 37);
+
+    // Both continue with identical bytes.
+    post_buffers(&mut dev, &mut mem, notify, &[8]);
+    let mut expected = [0u8; 8];
+    let mut resumed = rng.deterministic_source().clone();
+    resumed.fill_bytes(&mut expected);
+    assert_eq!(buffer(&mem, 1, 8), expected);
+}
@@ -3290,6 +3290,11 @@ pub enum RunExitKind {
     Assist,
     Exception,
     CpuExit,
+    HangSuspected,
+    SmiUnsupported,
+    UnknownPortIo,
+    UnknownMmio,
+    PowerOff,
 }
 
 #[wasm_bindgen]
@@ -3351,6 +3356,41 @@ impl RunExit {
                 executed,
                 detail: format!("{exit:?}"),
             },
+            aero_machine::RunExit::HangSuspected { details, .. } => Self {
+                kind: RunExitKind::HangSuspected,
+                executed,
+                detail: format!("{details:?}"),
+            },
+            aero_machine::RunExit::SmiUnsupported { .. } => Self {
+                kind: RunExitKind::SmiUnsupported,
+                executed,
+                detail: String::new(),
+            },
+            aero_machine::RunExit::PowerOff { .. } => Self {
+                kind: RunExitKind::PowerOff,
+                executed,
+                detail: String::new(),
+            },
+            aero_machine::RunExit::UnknownPortIo {
+                port, size, write, ..
+            } => Self {
+                kind: RunExitKind::UnknownPortIo,
+                executed,
+                detail: format!(
+                    "{} port {port:#06x} size {size}",
+                    if write { "write" } else { "read" }
+                ),
+            },
+            aero_machine::RunExit::UnknownMmio {
+                paddr, size, write, ..
+            } => Self {
+                kind: RunExitKind::UnknownMmio,
+                executed,
+                detail: format!(
+                    "{} paddr {paddr:#x} size {size}",
+                    if write { "write" } else { "read" }
+                ),
+            },
         }
     }
 }
@@ -3388,6 +3428,33 @@ pub enum MouseButtons {
     Forward = 0x10,
 }
 
+/// Host end of a guest serial port byte channel (see [`Machine::serial_channel`]).
+///
+/// JS can bridge this to an external transport (e.g. a WebSocket carrying a Windows kernel
+/// debugger session): forward `recv()` bytes to the socket and socket data to `send()`.
+#[wasm_bindgen]
+pub struct SerialChannel {
+    inner: aero_machine::SerialChannel,
+}
+
+#[wasm_bindgen]
+impl SerialChannel {
+    /// Queue bytes for delivery to the guest UART.
+    pub fn send(&self, bytes: &[u8]) {
+        self.inner.send(bytes);
+    }
+
+    /// Drain the bytes the guest has transmitted on this port.
+    pub fn recv(&self) -> Vec<u8> {
+        self.inner.recv()
+    }
+
+    /// Number of guest-transmitted bytes waiting for `recv()`.
+    pub fn recv_len(&self) -> u32 {
+        self.inner.recv_len().min(u32::MAX as usize) as u32
+    }
+}
+
 #[cfg(any(target_arch = "wasm32", test))]
 const AEROSPARSE_HEADER_SIZE_BYTES: usize = 64;
 
@@ -3509,6 +3576,7 @@ mod cow_base_format_tests {
             AeroSparseConfig {
                 disk_size_bytes: BASE_SIZE,
                 block_size_bytes: BASE_BLOCK_SIZE,
+                ..Default::default()
             },
         )
         .expect("create aerosparse base");
@@ -3948,6 +4016,9 @@ impl Machine {
             if let Some(v) = get_bool("enable_virtio_input")? {
                 cfg.enable_virtio_input = v;
             }
+            if let Some(v) = get_bool("enable_virtio_rng")? {
+                cfg.enable_virtio_rng = v;
+            }
             if let Some(v) = get_bool("enable_ahci")? {
                 cfg.enable_ahci = v;
             }
@@ -3993,6 +4064,15 @@ impl Machine {
             if let Some(v) = get_bool("enable_serial")? {
                 cfg.enable_serial = v;
             }
+            if let Some(v) = get_bool("enable_com2")? {
+                cfg.enable_com2 = v;
+            }
+            if let Some(v) = get_bool("enable_com3")? {
+                cfg.enable_com3 = v;
+            }
+            if let Some(v) = get_bool("enable_com4")? {
+                cfg.enable_com4 = v;
+            }
             if let Some(v) = get_bool("enable_i8042")? {
                 cfg.enable_i8042 = v;
             }
@@ -4208,7 +4288,21 @@ impl Machine {
     }
 
     pub fn reset(&mut self) {
-        self.inner.reset();
+        self.reset_with_kind(false);
+    }
+
+    /// Reset the machine as requested by a `ResetRequested` run exit (detail `"Cpu"` is a warm
+    /// reset, `"System"` a cold one).
+    ///
+    /// A warm reset keeps RTC/CMOS contents and AeroGPU VRAM and lets firmware resume through the
+    /// CMOS shutdown status byte; see `aero_machine::Machine::reset_with_kind`.
+    pub fn reset_with_kind(&mut self, warm: bool) {
+        let kind = if warm {
+            aero_platform::reset::ResetKind::Cpu
+        } else {
+            aero_platform::reset::ResetKind::System
+        };
+        self.inner.reset_with_kind(kind);
         self.mouse_buttons = 0;
         self.mouse_buttons_known = true;
 
@@ -4221,6 +4315,22 @@ impl Machine {
         }
     }
 
+    /// Source of the guest reset request the host has not acted on yet (Rust `Debug` formatting,
+    /// e.g. `"Port0xCF9"` or `"TripleFault { vector_history: [..] }"`), or `undefined`.
+    ///
+    /// A reported reset stays pending (and `run_slice` keeps reporting it) until `reset*`, a
+    /// snapshot restore or `cancel_pending_reset`; see `aero_machine::Machine::pending_reset`.
+    pub fn pending_reset(&self) -> Option<String> {
+        self.inner
+            .pending_reset()
+            .map(|source| format!("{source:?}"))
+    }
+
+    /// Drop the pending guest reset request and resume the guest on the next `run_slice`.
+    pub fn cancel_pending_reset(&mut self) {
+        self.inner.cancel_pending_reset();
+    }
+
     /// Set the preferred BIOS boot device for the next reset.
     pub fn set_boot_device(&mut self, device: MachineBootDevice) {
         let native = match device {
@@ -4417,6 +4527,7 @@ impl Machine {
                 let cfg = aero_storage::AeroSparseConfig {
                     disk_size_bytes: size_bytes,
                     block_size_bytes: 1024 * 1024,
+                    ..Default::default()
                 };
                 aero_storage::AeroSparseDisk::create(backend, cfg)
                     .map_err(|e| opfs_context_error_to_js("Machine.set_disk_opfs", &path, e))?
@@ -4823,6 +4934,7 @@ impl Machine {
             aero_storage::AeroSparseConfig {
                 disk_size_bytes,
                 block_size_bytes,
+                ..Default::default()
             },
         )
         .map_err(|e| {
@@ -5268,6 +5380,40 @@ impl Machine {
         RunExit::from_native(exit)
     }
 
+    /// Run until `guest_us` microseconds of guest time have elapsed (see
+    /// `aero_machine::Machine::run_for`).
+    pub fn run_for_us(&mut self, guest_us: u32) -> RunExit {
+        let exit = self.inner.run_for(u64::from(guest_us) * 1000);
+
+        #[cfg(all(target_arch = "wasm32", feature = "wasm-threaded"))]
+        {
+            self.maybe_publish_legacy_scanout_from_vga();
+        }
+
+        RunExit::from_native(exit)
+    }
+
+    /// Run until the JS callback `host_check` returns a truthy value (see
+    /// `aero_machine::Machine::run_until_deadline`). The callback is polled between internal
+    /// batches, e.g. to compare `performance.now()` against the next animation frame. A throwing
+    /// callback stops the run.
+    #[cfg(target_arch = "wasm32")]
+    pub fn run_until_deadline(&mut self, host_check: js_sys::Function) -> RunExit {
+        let exit = self.inner.run_until_deadline(&mut || {
+            host_check
+                .call0(&JsValue::NULL)
+                .map(|value| value.is_truthy())
+                .unwrap_or(true)
+        });
+
+        #[cfg(feature = "wasm-threaded")]
+        {
+            self.maybe_publish_legacy_scanout_from_vga();
+        }
+
+        RunExit::from_native(exit)
+    }
+
     // -------------------------------------------------------------------------
     // Scanout state (threaded WASM only)
     // -------------------------------------------------------------------------
@@ -5446,6 +5592,8 @@ impl Machine {
             height: 0,
             pitch_bytes: 0,
             format: SCANOUT_FORMAT_B8G8R8X8,
+            slot0_offset: 0,
+            slot1_offset: 0,
         });
     }
 
@@ -5559,6 +5707,78 @@ impl Machine {
         self.inner.serial_output_len().min(u64::from(u32::MAX)) as u32
     }
 
+    /// Returns and clears any accumulated output of COM port `port` (`0` = COM1 .. `3` = COM4).
+    pub fn serial_port_output(&mut self, port: u32) -> Vec<u8> {
+        self.inner.take_serial_port_output(port as usize)
+    }
+
+    /// Return the current output length of COM port `port` without copying the bytes into JS.
+    pub fn serial_port_output_len(&mut self, port: u32) -> u32 {
+        self.inner
+            .serial_port_output_len(port as usize)
+            .min(u64::from(u32::MAX)) as u32
+    }
+
+    /// Queue host input bytes in the receive FIFO of COM port `port`.
+    ///
+    /// Returns `false` when the port is not enabled.
+    pub fn serial_write_input(&mut self, port: u32, bytes: &[u8]) -> bool {
+        self.inner.serial_write_input(port as usize, bytes)
+    }
+
+    /// Attach (or return the already attached) byte channel for COM port `port`.
+    ///
+    /// While attached, guest output on the port is delivered to the channel instead of
+    /// `serial_port_output`. Returns `undefined` when the port is not enabled.
+    pub fn serial_channel(&mut self, port: u32) -> Option<SerialChannel> {
+        self.inner
+            .serial_channel(port as usize)
+            .map(|inner| SerialChannel { inner })
+    }
+
+    /// Detach the byte channel from COM port `port`, resuming output logging.
+    pub fn detach_serial_channel(&mut self, port: u32) {
+        self.inner.detach_serial_channel(port as usize);
+    }
+
+    /// Route COM port `port` through the kernel debugger bridge (see `kd_take_from_guest`).
+    ///
+    /// With `framing` set, guest output is delimited into KD packets retrieved via
+    /// `kd_next_frame`. Returns `false` when the port is not enabled.
+    pub fn enable_kd_bridge(&mut self, port: u32, framing: bool) -> bool {
+        if !self.inner.enable_kd_bridge(port as usize) {
+            return false;
+        }
+        self.inner.kd_set_packet_framing(framing);
+        true
+    }
+
+    pub fn disable_kd_bridge(&mut self) {
+        self.inner.disable_kd_bridge();
+    }
+
+    /// Drain the raw guest output of the bridged port (unframed mode).
+    pub fn kd_take_from_guest(&mut self) -> Vec<u8> {
+        self.inner
+            .kd_take_from_guest()
+            .into_iter()
+            .flat_map(|chunk| chunk.bytes)
+            .collect()
+    }
+
+    /// Pop the next delimited KD packet (or run of non-packet bytes) from the bridged port.
+    ///
+    /// Packets are returned verbatim, so callers can tell data (`0x30` leader) and control
+    /// (`0x69` leader) packets apart by their first byte.
+    pub fn kd_next_frame(&mut self) -> Option<Vec<u8>> {
+        self.inner.kd_next_frame().map(|frame| frame.bytes)
+    }
+
+    /// Queue debugger bytes in the bridged port's receive FIFO.
+    pub fn kd_send_to_guest(&mut self, bytes: &[u8]) -> bool {
+        self.inner.kd_send_to_guest(bytes)
+    }
+
     /// Returns and clears any accumulated DebugCon output (I/O port `0xE9`).
     ///
     /// Many emulators (Bochs/QEMU) expose a "debug console" sink at port `0xE9`, allowing guests to
@@ -5580,8 +5800,106 @@ impl Machine {
     ///
     /// Call this before reading the framebuffer via `display_framebuffer_*` or before sampling
     /// `display_width()`/`display_height()` after the guest changes video modes.
-    pub fn display_present(&mut self) {
-        self.inner.display_present();
+    ///
+    /// Returns `false` (keeping the previous framebuffer) when the guest has not touched the
+    /// display since the last present, so callers can skip re-uploading the frame.
+    pub fn display_present(&mut self) -> bool {
+        self.inner.display_present(false)
+    }
+
+    /// Re-render the display even if the guest has not touched it since the last present.
+    pub fn display_present_forced(&mut self) {
+        self.inner.display_present(true);
+    }
+
+    /// Monotonic display damage counter (see `aero_machine::Machine::display_generation`).
+    ///
+    /// Exposed to JS as a `BigInt`.
+    pub fn display_generation(&self) -> u64 {
+        self.inner.display_generation()
+    }
+
+    /// Guest instructions retired across all vCPUs since the last `reset_perf_counters` (see
+    /// `aero_machine::Machine::perf_counters`).
+    ///
+    /// Exposed to JS as a `BigInt`; sample it periodically to derive a MIPS readout.
+    pub fn perf_total_instructions(&self) -> u64 {
+        self.inner.perf_counters().total_instructions()
+    }
+
+    /// Guest port I/O accesses (reads + writes) since the last `reset_perf_counters`.
+    pub fn perf_port_io_accesses(&self) -> u64 {
+        let io = self.inner.perf_counters().port_io;
+        io.reads.wrapping_add(io.writes)
+    }
+
+    /// Guest MMIO accesses (reads + writes, all counted windows) since the last
+    /// `reset_perf_counters`.
+    pub fn perf_mmio_accesses(&self) -> u64 {
+        let mmio = self.inner.perf_counters().mmio;
+        [
+            mmio.lapic,
+            mmio.ioapic,
+            mmio.hpet,
+            mmio.pci,
+            mmio.vga_legacy,
+            mmio.ecam,
+        ]
+        .iter()
+        .fold(0u64, |acc, c| {
+            acc.wrapping_add(c.reads).wrapping_add(c.writes)
+        })
+    }
+
+    /// Zero the machine performance counters.
+    pub fn reset_perf_counters(&mut self) {
+        self.inner.reset_perf_counters();
+    }
+
+    /// Arm the guest hang watchdog with a threshold in milliseconds of guest time (`0` disarms it).
+    ///
+    /// When it fires, `run_slice` returns a `RunExitKind::HangSuspected` exit whose `detail`
+    /// describes the stalled vCPUs (see `aero_machine::Machine::set_hang_watchdog`).
+    pub fn set_hang_watchdog_ms(&mut self, threshold_ms: u32) {
+        self.inner.set_hang_watchdog(
+            (threshold_ms != 0).then(|| std::time::Duration::from_millis(u64::from(threshold_ms))),
+        );
+    }
+
+    /// Feed the virtio-rng device from `crypto.getRandomValues` instead of the deterministic
+    /// stream seeded from `MachineConfig::rng_seed` (see `aero_machine::Machine::set_entropy_source`).
+    ///
+    /// Returns `false` if the machine has no virtio-rng device.
+    #[cfg(target_arch = "wasm32")]
+    pub fn install_crypto_entropy_source(&mut self) -> Result<bool, JsValue> {
+        use wasm_bindgen::JsCast;
+
+        struct CryptoEntropySource {
+            crypto: JsValue,
+            get_random_values: js_sys::Function,
+        }
+
+        impl aero_machine::EntropySource for CryptoEntropySource {
+            fn fill_bytes(&mut self, buf: &mut [u8]) {
+                // `getRandomValues` rejects requests larger than 64KiB.
+                for chunk in buf.chunks_mut(65536) {
+                    let array = Uint8Array::new_with_length(chunk.len() as u32);
+                    if self.get_random_values.call1(&self.crypto, &array).is_ok() {
+                        array.copy_to(chunk);
+                    }
+                }
+            }
+        }
+
+        let crypto = Reflect::get(&js_sys::global(), &JsValue::from_str("crypto"))?;
+        let get_random_values: js_sys::Function =
+            Reflect::get(&crypto, &JsValue::from_str("getRandomValues"))?
+                .dyn_into()
+                .map_err(|_| JsValue::from_str("crypto.getRandomValues is not available"))?;
+        Ok(self.inner.set_entropy_source(Box::new(CryptoEntropySource {
+            crypto,
+            get_random_values,
+        })))
     }
 
     /// Current display output width in pixels (0 if no display scanout is available).
@@ -5654,7 +5972,7 @@ impl Machine {
     /// Note: this calls [`Machine::display_present`] internally, so calling it invalidates any
     /// previously returned framebuffer pointer.
     pub fn display_framebuffer_copy_rgba8888(&mut self) -> Vec<u8> {
-        self.inner.display_present();
+        self.inner.display_present(false);
         let fb: &[u32] = self.inner.display_framebuffer();
         if fb.is_empty() {
             return Vec::new();
@@ -5714,6 +6032,45 @@ impl Machine {
         u32::try_from(base).unwrap_or(0)
     }
 
+    /// Byte offset of the AeroGPU BAR1 VRAM backing within wasm linear memory.
+    ///
+    /// Returns `0` if AeroGPU is absent.
+    ///
+    /// # Safety contract (JS/host)
+    /// The view stays valid only while [`Machine::aerogpu_vram_generation`] is unchanged: a cold
+    /// reset or snapshot restore clears or replaces VRAM and bumps the generation. In threaded
+    /// builds a GPU worker may read/write this region of the shared memory directly, but must not
+    /// do so while the machine is running a slice on another thread.
+    #[cfg(target_arch = "wasm32")]
+    pub fn aerogpu_vram_ptr(&self) -> u32 {
+        self.inner
+            .aerogpu_vram_export()
+            .map_or(0, |export| export.linear_memory_offset())
+    }
+
+    /// See [`Machine::aerogpu_vram_ptr`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn aerogpu_vram_ptr(&self) -> u32 {
+        0
+    }
+
+    /// Length in bytes of the AeroGPU VRAM backing (see [`Machine::aerogpu_vram_ptr`]).
+    ///
+    /// Returns `0` if AeroGPU is absent.
+    pub fn aerogpu_vram_len_bytes(&self) -> u32 {
+        self.inner
+            .aerogpu_vram_export()
+            .map_or(0, |export| u32::try_from(export.len).unwrap_or(u32::MAX))
+    }
+
+    /// Low 32 bits of the AeroGPU VRAM generation.
+    ///
+    /// Re-query [`Machine::aerogpu_vram_ptr`] and [`Machine::aerogpu_vram_len_bytes`] whenever this
+    /// changes.
+    pub fn aerogpu_vram_generation(&self) -> u32 {
+        self.inner.aerogpu_vram_generation() as u32
+    }
+
     // -------------------------------------------------------------------------
     // Legacy VGA/SVGA scanout (BIOS text mode + VBE graphics)
     // -------------------------------------------------------------------------
@@ -5956,6 +6313,27 @@ impl Machine {
         self.mouse_buttons_known = false;
     }
 
+    /// Select the scanout head that `MouseAbsolute` input batch events are relative to.
+    ///
+    /// Host policy; not part of snapshots.
+    pub fn set_input_focus(&mut self, head: u8) {
+        self.inner.set_input_focus(head);
+    }
+
+    /// Set whether the guest has grabbed the keyboard.
+    ///
+    /// While not grabbed, input batch key presses of the filtered HID usages (by default the
+    /// Windows/GUI keys) are dropped so browser shortcuts keep working. Host policy; not part of
+    /// snapshots.
+    pub fn set_keyboard_grab(&mut self, grabbed: bool) {
+        self.inner.set_keyboard_grab(grabbed);
+    }
+
+    /// Replace the HID usages (Usage Page 0x07) filtered while the keyboard is not grabbed.
+    pub fn set_keyboard_grab_filter(&mut self, usages: &[u8]) {
+        self.inner.set_keyboard_grab_filter(usages);
+    }
+
     /// Inject a browser-style keyboard event into the guest.
     ///
     /// `code` must be a DOM `KeyboardEvent.code` string (e.g. `"KeyA"`, `"Enter"`, `"ArrowUp"`).
@@ -6795,6 +7173,41 @@ impl Machine {
         Ok(())
     }
 
+    /// Set the guest RTC to the host wall-clock time (`Date.now()`), e.g. after
+    /// [`Machine::restore_snapshot`]. Monotonic guest time is not affected.
+    pub fn resync_wall_clock(&mut self, now_unix_ms: f64) {
+        self.inner.resync_wall_clock(now_unix_ms as i64);
+    }
+
+    /// Serialize the guest's non-volatile settings (CMOS RAM) for the host to store next to the
+    /// disk, e.g. in OPFS.
+    pub fn export_nvram(&self) -> Vec<u8> {
+        self.inner.export_nvram()
+    }
+
+    /// Load settings previously returned by [`Machine::export_nvram`]. Firmware-owned fields are
+    /// re-derived from the machine configuration.
+    pub fn import_nvram(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.inner
+            .import_nvram(bytes)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Press the ACPI power button to request an orderly guest shutdown. Completion is reported
+    /// as a `PowerOff` run exit. Returns `false` if the machine has no ACPI PM device.
+    pub fn press_power_button(&mut self) -> bool {
+        self.inner.press_power_button()
+    }
+
+    /// Guest power state: `0` running, `1` sleeping (S1..S3), `2` soft-off (S4/S5).
+    pub fn power_state(&self) -> u8 {
+        match self.inner.power_state() {
+            aero_machine::GuestPowerState::Running => 0,
+            aero_machine::GuestPowerState::Sleeping => 1,
+            aero_machine::GuestPowerState::SoftOff => 2,
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub async fn snapshot_full_to_opfs(&mut self, path: String) -> Result<(), JsValue> {
         let mut file = OpfsSyncFile::create(&path)
@@ -7107,6 +7520,7 @@ mod machine_opfs_disk_tests {
             aero_storage::AeroSparseConfig {
                 disk_size_bytes: size_bytes * 2,
                 block_size_bytes: 32 * 1024,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -7497,6 +7911,7 @@ mod reattach_restored_disks_from_opfs_tests {
             aero_storage::AeroSparseConfig {
                 disk_size_bytes,
                 block_size_bytes: 512,
+                ..Default::default()
             },
         )?;
         disk.flush()?;
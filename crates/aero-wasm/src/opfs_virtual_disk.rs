@@ -84,6 +84,7 @@ mod tests {
             AeroSparseConfig {
                 disk_size_bytes,
                 block_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
         )
         .expect("create aerosparse should succeed");
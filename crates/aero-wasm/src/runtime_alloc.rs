@@ -28,7 +28,7 @@ use crate::guest_layout::{RUNTIME_RESERVED_BYTES, WASM_PAGE_BYTES};
 //   memory (required for cross-worker presentation plumbing in the threaded build).
 //
 // This stays intentionally small (<< 1 page) so it doesn't materially reduce available heap.
-const HEAP_TAIL_GUARD_BYTES: usize = 144; // 32B ScanoutState + 48B CursorState + 64B probe window
+const HEAP_TAIL_GUARD_BYTES: usize = 160; // 48B ScanoutState + 48B CursorState + 64B probe window
 
 // Ensure the tail guard is large enough for the JS-side memory wiring probes.
 //
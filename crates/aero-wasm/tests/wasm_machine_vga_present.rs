@@ -11,7 +11,7 @@ const _: () = {
 // Keep constants in sync with:
 // - `crates/aero-shared/src/scanout_state.rs`
 // - `web/src/ipc/scanout_state.ts`
-const SCANOUT_STATE_U32_LEN: usize = 8;
+const SCANOUT_STATE_U32_LEN: usize = 12;
 const SCANOUT_STATE_BYTE_LEN: u32 = (SCANOUT_STATE_U32_LEN as u32) * 4;
 const SCANOUT_STATE_GENERATION_BUSY_BIT: u32 = 1 << 31;
 
@@ -105,6 +105,10 @@ impl PortIoDevice for A20Gate {
         // Power-on value: A20 gate follows the chipset state; reset bit is cleared.
         self.value = if self.a20.enabled() { 0x02 } else { 0x00 };
     }
+
+    fn debug_name(&self) -> &'static str {
+        "a20-gate"
+    }
 }
 
 #[cfg(test)]
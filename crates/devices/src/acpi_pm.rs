@@ -4,7 +4,9 @@
 //! registers described by the FADT:
 //! - `SMI_CMD` + `ACPI_ENABLE`/`ACPI_DISABLE` handshake to toggle `PM1a_CNT.SCI_EN`.
 //! - `PM1a_EVT` (status + enable) and `PM1a_CNT` (control).
-//! - `PM_TMR` (24-bit free-running timer at 3.579545MHz) and a minimal `GPE0` block.
+//! - `PM_TMR` (24-bit free-running timer at 3.579545MHz).
+//! - The `GPE0` block (status + enable halves). Machine-internal event sources raise GPEs with
+//!   [`AcpiPmIo::assert_gpe`]; the guest acknowledges them write-1-to-clear.
 //!
 //! This device also watches `PM1a_CNT.SLP_TYP/SLP_EN` and surfaces guest sleep
 //! requests via host callbacks.
@@ -50,6 +52,12 @@ pub const PM1_STS_PWRBTN: u16 = 1 << 8;
 /// position in `PM1_EN` (SLPBTN_EN).
 pub const PM1_STS_SLPBTN: u16 = 1 << 9;
 
+/// `PM1_STS.RTC_STS` (ACPI spec).
+///
+/// Fixed-feature RTC status (bit 10). The corresponding enable bit uses the same bit position in
+/// `PM1_EN` (RTC_EN).
+pub const PM1_STS_RTC: u16 = 1 << 10;
+
 /// `PM1_STS.WAK_STS` (ACPI spec).
 ///
 /// Wake status bit indicating the system is waking from a sleep state.
@@ -97,7 +105,10 @@ const PM1_CNT_SLP_TYP_SHIFT: u16 = 10;
 const PM1_CNT_SLP_TYP_MASK: u16 = 0b111 << PM1_CNT_SLP_TYP_SHIFT;
 const PM1_CNT_SLP_EN: u16 = 1 << 13;
 
-const PM_TIMER_FREQUENCY_HZ: u128 = 3_579_545;
+/// Input frequency of the ACPI PM timer (`PM_TMR`).
+pub const PM_TIMER_HZ: u64 = 3_579_545;
+
+const PM_TIMER_FREQUENCY_HZ: u128 = PM_TIMER_HZ as u128;
 const PM_TIMER_MASK_24BIT: u32 = 0x00FF_FFFF;
 const NS_PER_SEC: u128 = 1_000_000_000;
 
@@ -305,6 +316,21 @@ impl<C: Clock> AcpiPmIo<C> {
         self.update_sci();
     }
 
+    /// Number of general-purpose events in the GPE0 block (8 per status byte).
+    pub fn gpe0_count(&self) -> usize {
+        self.gpe0_sts.len() * 8
+    }
+
+    /// Assert general-purpose event `gpe`: latch its `GPE0_STS` bit and refresh SCI.
+    ///
+    /// The status bit stays set until the guest clears it by writing 1 to it, and only raises SCI
+    /// while the matching `GPE0_EN` bit (and `PM1_CNT.SCI_EN`) is set. Events beyond
+    /// [`AcpiPmIo::gpe0_count`] are ignored. The numbers the DSDT handles for Aero event sources
+    /// are `aero_acpi::AERO_GPE_*`.
+    pub fn assert_gpe(&mut self, gpe: u8) {
+        self.trigger_gpe0(usize::from(gpe / 8), 1 << (gpe % 8));
+    }
+
     /// Inject bits into a GPE0 status byte and refresh SCI.
     pub fn trigger_gpe0(&mut self, byte_index: usize, sts_bits: u8) {
         if let Some(slot) = self.gpe0_sts.get_mut(byte_index) {
@@ -808,6 +834,10 @@ impl<C: Clock + 'static> PortIoDevice for AcpiPmIo<C> {
     fn reset(&mut self) {
         self.reset_state();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "acpi-pm"
+    }
 }
 
 pub type SharedAcpiPmIo<C = NullClock> = Rc<RefCell<AcpiPmIo<C>>>;
@@ -838,6 +868,10 @@ impl<C: Clock + 'static> PortIoDevice for AcpiPmPort<C> {
     fn reset(&mut self) {
         self.pm.borrow_mut().reset();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "acpi-pm"
+    }
 }
 
 /// Register the ACPI PM fixed-feature I/O ports on an [`IoPortBus`].
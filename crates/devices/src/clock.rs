@@ -1 +1 @@
-pub use aero_interrupts::clock::{Clock, ManualClock, NullClock};
+pub use aero_interrupts::clock::{Clock, ManualClock, NullClock, TimerCatchupPolicy};
@@ -60,6 +60,10 @@ impl PortIoDevice for DebugCon {
     fn reset(&mut self) {
         self.log.borrow_mut().clear();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "debugcon"
+    }
 }
 
 /// Register a [`DebugCon`] device on `bus` at [`DEBUGCON_PORT`].
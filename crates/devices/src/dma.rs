@@ -1,17 +1,216 @@
+use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
+use aero_io_snapshot::io::state::{
+    IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
+};
 use aero_platform::io::{IoPortBus, PortIoDevice};
+use memory::MemoryBus;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
-/// Minimal 8237 DMA controller model.
+/// Number of 8-bit channels on the primary (`0x00..=0x0F`) controller.
+pub const DMA8_CHANNELS: usize = 4;
+
+/// Total number of channels: 8-bit channels 0-3 on the primary controller and 16-bit channels
+/// 4-7 on the secondary (`0xC0..=0xDF`) controller.
+pub const DMA_CHANNELS: usize = 8;
+
+/// Channel 4 cascades the primary controller into the secondary one and never transfers data.
+pub const DMA_CASCADE_CHANNEL: usize = 4;
+
+/// Page register port for each channel (channel 0..=7). Channel 4's page register is unused.
+const PAGE_PORTS: [u16; DMA_CHANNELS] = [0x87, 0x83, 0x81, 0x82, 0x8F, 0x8B, 0x89, 0x8A];
+
+// Controller register indices. The primary controller decodes them at ports `0x00..=0x0F`, the
+// secondary controller at the even ports `0xC0 + 2 * index`.
+const PORT_STATUS_COMMAND: u16 = 0x08;
+const PORT_REQUEST: u16 = 0x09;
+const PORT_SINGLE_MASK: u16 = 0x0A;
+const PORT_MODE: u16 = 0x0B;
+const PORT_CLEAR_FLIP_FLOP: u16 = 0x0C;
+const PORT_MASTER_CLEAR: u16 = 0x0D;
+const PORT_CLEAR_MASK: u16 = 0x0E;
+const PORT_WRITE_ALL_MASK: u16 = 0x0F;
+
+const SECONDARY_BASE: u16 = 0xC0;
+
+const MODE_TRANSFER_MASK: u8 = 0x0C;
+const MODE_TRANSFER_VERIFY: u8 = 0x00;
+const MODE_TRANSFER_WRITE: u8 = 0x04;
+const MODE_TRANSFER_READ: u8 = 0x08;
+const MODE_AUTOINIT: u8 = 1 << 4;
+const MODE_DECREMENT: u8 = 1 << 5;
+const MODE_SELECT_MASK: u8 = 0xC0;
+const MODE_SELECT_DEMAND: u8 = 0x00;
+const MODE_SELECT_SINGLE: u8 = 0x40;
+const MODE_SELECT_BLOCK: u8 = 0x80;
+const MODE_SELECT_CASCADE: u8 = 0xC0;
+
+const COMMAND_DISABLE: u8 = 1 << 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DmaChannel {
+    base_address: u16,
+    current_address: u16,
+    base_count: u16,
+    current_count: u16,
+    mode: u8,
+    masked: bool,
+}
+
+impl Default for DmaChannel {
+    fn default() -> Self {
+        Self {
+            base_address: 0,
+            current_address: 0,
+            base_count: 0,
+            current_count: 0,
+            mode: 0,
+            // Channels power up masked.
+            masked: true,
+        }
+    }
+}
+
+/// Outcome of a device-initiated transfer on a DMA channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DmaTransfer {
+    /// Number of bytes moved before the transfer stopped.
+    pub bytes: usize,
+    /// Whether the channel reached terminal count (TC) during this transfer.
+    pub terminal_count: bool,
+}
+
+/// One DACK cycle handed to a [`DmaChannelClient`].
+///
+/// A unit is one byte on channels 0-3 and one little-endian word on channels 5-7.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DmaCycle<'a> {
+    /// Device-to-memory ("write" transfer): the device fills the buffer.
+    ToMemory(&'a mut [u8]),
+    /// Memory-to-device ("read" transfer): the buffer holds the guest memory contents.
+    FromMemory(&'a [u8]),
+    /// Verify transfer: addresses and counts advance but no data moves.
+    Verify,
+}
+
+/// An ISA device attached to a DMA channel and serviced by [`Dma8237::service`].
+///
+/// Callbacks run while the controller is borrowed, so clients must not access the controller
+/// from them.
+pub trait DmaChannelClient {
+    /// DREQ: whether the device is requesting service.
+    fn dma_request(&self) -> bool;
+
+    /// DACK: one unit is being transferred for the device.
+    fn dma_ack(&mut self, cycle: DmaCycle<'_>);
+
+    /// TC was signalled on the cycle just acknowledged.
+    fn dma_terminal_count(&mut self) {}
+}
+
+pub type SharedDmaChannelClient = Rc<RefCell<dyn DmaChannelClient>>;
+
+/// Errors returned by [`Dma8237::register_client`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaClientError {
+    /// The channel is not in `0..DMA_CHANNELS`.
+    InvalidChannel(usize),
+    /// Channel 4 is reserved for cascading the primary controller.
+    CascadeChannel,
+    /// Another client is already registered on the channel.
+    ChannelInUse(usize),
+}
+
+impl fmt::Display for DmaClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DmaClientError::InvalidChannel(channel) => {
+                write!(f, "DMA channel {channel} does not exist")
+            }
+            DmaClientError::CascadeChannel => {
+                write!(
+                    f,
+                    "DMA channel {DMA_CASCADE_CHANNEL} is reserved for cascade"
+                )
+            }
+            DmaClientError::ChannelInUse(channel) => {
+                write!(f, "DMA channel {channel} already has a client")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DmaClientError {}
+
+/// Minimal 8237 DMA controller model (a primary and a secondary controller, as on the PC/AT).
 ///
 /// Windows 7 doesn't rely on legacy DMA for most devices, but probes the
-/// controller for compatibility. This implementation is a "register file"
-/// stub: it stores written values and returns them on reads, without actually
-/// performing DMA transfers.
-#[derive(Default, Clone, Debug)]
+/// controller for compatibility. Ports without modeled behaviour act as a "register file"
+/// (writes are stored and returned on reads). Both controllers model address/count programming,
+/// masking, mode, request and status registers. Devices move data either by pushing it through
+/// [`Dma8237::transfer_to_memory`] / [`Dma8237::transfer_from_memory`] (the floppy controller), or
+/// by registering a [`DmaChannelClient`] and letting [`Dma8237::service`] run the transfer in the
+/// programmed single, demand or block mode.
+///
+/// Channels 5-7 count 16-bit words: the address register holds bits 1-16 of the physical address
+/// and the page register supplies bits 17-23. Like real hardware, the address counter wraps within
+/// its 64 KiB (128 KiB for 16-bit channels) page instead of carrying into the page register.
+#[derive(Clone)]
 pub struct Dma8237 {
     regs: HashMap<u16, u8>,
+    channels: [DmaChannel; DMA_CHANNELS],
+    /// Per-controller byte-pointer flip-flop (index 0 = primary, 1 = secondary).
+    flip_flop: [bool; 2],
+    /// Per-controller terminal-count status bits (low nibble).
+    status: [u8; 2],
+    command: [u8; 2],
+    /// Software DMA requests (bit per channel). Block transfers also hold their request bit until
+    /// terminal count.
+    request: u8,
+    /// Host wiring; not part of the guest-visible state.
+    clients: [Option<SharedDmaChannelClient>; DMA_CHANNELS],
+}
+
+impl Default for Dma8237 {
+    fn default() -> Self {
+        Self {
+            regs: HashMap::new(),
+            channels: [DmaChannel::default(); DMA_CHANNELS],
+            flip_flop: [false; 2],
+            status: [0; 2],
+            command: [0; 2],
+            request: 0,
+            clients: Default::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Dma8237 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clients: Vec<usize> = (0..DMA_CHANNELS)
+            .filter(|&channel| self.clients[channel].is_some())
+            .collect();
+        f.debug_struct("Dma8237")
+            .field("regs", &self.regs)
+            .field("channels", &self.channels)
+            .field("flip_flop", &self.flip_flop)
+            .field("status", &self.status)
+            .field("command", &self.command)
+            .field("request", &self.request)
+            .field("clients", &clients)
+            .finish()
+    }
+}
+
+/// Map an I/O port to `(controller, register index)` for the controller register files.
+fn decode_controller_port(port: u16) -> Option<(usize, u16)> {
+    match port {
+        0x00..=0x0F => Some((0, port)),
+        SECONDARY_BASE..=0xDF if port & 1 == 0 => Some((1, (port - SECONDARY_BASE) / 2)),
+        _ => None,
+    }
 }
 
 impl Dma8237 {
@@ -19,16 +218,491 @@ impl Dma8237 {
         Self::default()
     }
 
+    /// Reset the controller to its power-on state, keeping registered clients.
     pub fn reset(&mut self) {
-        self.regs.clear();
+        let clients = std::mem::take(&mut self.clients);
+        *self = Self::default();
+        self.clients = clients;
+    }
+
+    /// Attach `client` to `channel` so [`Dma8237::service`] runs its transfers.
+    pub fn register_client(
+        &mut self,
+        channel: usize,
+        client: SharedDmaChannelClient,
+    ) -> Result<(), DmaClientError> {
+        if channel >= DMA_CHANNELS {
+            return Err(DmaClientError::InvalidChannel(channel));
+        }
+        if channel == DMA_CASCADE_CHANNEL {
+            return Err(DmaClientError::CascadeChannel);
+        }
+        let slot = &mut self.clients[channel];
+        if slot.is_some() {
+            return Err(DmaClientError::ChannelInUse(channel));
+        }
+        *slot = Some(client);
+        Ok(())
+    }
+
+    /// Detach and return the client registered on `channel`, if any.
+    pub fn unregister_client(&mut self, channel: usize) -> Option<SharedDmaChannelClient> {
+        self.clients.get_mut(channel)?.take()
     }
 
-    pub fn read_u8(&self, port: u16) -> u8 {
-        self.regs.get(&port).copied().unwrap_or(0)
+    /// Whether a client is registered on `channel`.
+    pub fn has_client(&self, channel: usize) -> bool {
+        self.clients.get(channel).is_some_and(Option::is_some)
+    }
+
+    pub fn read_u8(&mut self, port: u16) -> u8 {
+        let Some((ctrl, reg)) = decode_controller_port(port) else {
+            return self.regs.get(&port).copied().unwrap_or(0);
+        };
+        match reg {
+            0x00..=0x07 => {
+                let ch = &self.channels[ctrl * 4 + usize::from(reg / 2)];
+                let value = if reg & 1 == 0 {
+                    ch.current_address
+                } else {
+                    ch.current_count
+                };
+                let byte = if self.flip_flop[ctrl] {
+                    (value >> 8) as u8
+                } else {
+                    value as u8
+                };
+                self.flip_flop[ctrl] = !self.flip_flop[ctrl];
+                byte
+            }
+            PORT_STATUS_COMMAND => {
+                // Reading the status register clears the terminal-count bits.
+                let status = self.status[ctrl] | (self.pending_requests(ctrl) << 4);
+                self.status[ctrl] = 0;
+                status
+            }
+            PORT_WRITE_ALL_MASK => self.channels[ctrl * 4..ctrl * 4 + 4]
+                .iter()
+                .enumerate()
+                .fold(0xF0, |acc, (idx, ch)| acc | (u8::from(ch.masked) << idx)),
+            _ => self.regs.get(&port).copied().unwrap_or(0),
+        }
     }
 
     pub fn write_u8(&mut self, port: u16, value: u8) {
         self.regs.insert(port, value);
+        let Some((ctrl, reg)) = decode_controller_port(port) else {
+            return;
+        };
+        let base = ctrl * 4;
+        match reg {
+            0x00..=0x07 => {
+                let high = self.flip_flop[ctrl];
+                self.flip_flop[ctrl] = !high;
+                let ch = &mut self.channels[base + usize::from(reg / 2)];
+                let (base, current) = if reg & 1 == 0 {
+                    (&mut ch.base_address, &mut ch.current_address)
+                } else {
+                    (&mut ch.base_count, &mut ch.current_count)
+                };
+                *base = if high {
+                    (*base & 0x00FF) | (u16::from(value) << 8)
+                } else {
+                    (*base & 0xFF00) | u16::from(value)
+                };
+                *current = *base;
+            }
+            PORT_STATUS_COMMAND => self.command[ctrl] = value,
+            PORT_REQUEST => {
+                let bit = 1u8 << (base + usize::from(value & 3));
+                if value & 0x04 != 0 {
+                    self.request |= bit;
+                } else {
+                    self.request &= !bit;
+                }
+            }
+            PORT_SINGLE_MASK => {
+                self.channels[base + usize::from(value & 3)].masked = value & 0x04 != 0;
+            }
+            PORT_MODE => {
+                self.channels[base + usize::from(value & 3)].mode = value;
+            }
+            PORT_CLEAR_FLIP_FLOP => self.flip_flop[ctrl] = false,
+            PORT_MASTER_CLEAR => {
+                self.flip_flop[ctrl] = false;
+                self.status[ctrl] = 0;
+                self.command[ctrl] = 0;
+                self.request &= !(0x0F << base);
+                for ch in &mut self.channels[base..base + 4] {
+                    ch.masked = true;
+                }
+            }
+            PORT_CLEAR_MASK => {
+                for ch in &mut self.channels[base..base + 4] {
+                    ch.masked = false;
+                }
+            }
+            PORT_WRITE_ALL_MASK => {
+                for (idx, ch) in self.channels[base..base + 4].iter_mut().enumerate() {
+                    ch.masked = value & (1 << idx) != 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether `channel` is currently masked (or does not exist).
+    pub fn channel_masked(&self, channel: usize) -> bool {
+        self.channels.get(channel).is_none_or(|ch| ch.masked)
+    }
+
+    /// DREQ lines (software requests and client requests) of controller `ctrl`, one bit per
+    /// channel.
+    fn pending_requests(&self, ctrl: usize) -> u8 {
+        (0..4).fold(0, |acc, idx| {
+            acc | (u8::from(self.dreq(ctrl * 4 + idx)) << idx)
+        })
+    }
+
+    fn dreq(&self, channel: usize) -> bool {
+        self.request & (1 << channel) != 0
+            || self.clients[channel]
+                .as_ref()
+                .is_some_and(|client| client.borrow().dma_request())
+    }
+
+    /// Whether `channel` may run a cycle: unmasked, not cascade, and its controller enabled.
+    fn channel_ready(&self, channel: usize) -> bool {
+        let ch = &self.channels[channel];
+        channel != DMA_CASCADE_CHANNEL
+            && !ch.masked
+            && ch.mode & MODE_SELECT_MASK != MODE_SELECT_CASCADE
+            && self.command[channel / 4] & COMMAND_DISABLE == 0
+    }
+
+    fn unit_len(channel: usize) -> usize {
+        if channel < DMA8_CHANNELS {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Physical address of the channel's next unit; advances address/count by one unit and
+    /// handles terminal count (autoinit reload, or masking the channel). Returns the address and
+    /// whether TC was reached.
+    fn step(&mut self, channel: usize) -> (u64, bool) {
+        let page = u64::from(self.regs.get(&PAGE_PORTS[channel]).copied().unwrap_or(0));
+        let ch = &mut self.channels[channel];
+        let paddr = if channel < DMA8_CHANNELS {
+            (page << 16) | u64::from(ch.current_address)
+        } else {
+            ((page & 0xFE) << 16) | (u64::from(ch.current_address) << 1)
+        };
+        ch.current_address = if ch.mode & MODE_DECREMENT != 0 {
+            ch.current_address.wrapping_sub(1)
+        } else {
+            ch.current_address.wrapping_add(1)
+        };
+
+        // The count register holds "units - 1"; TC is reached when it wraps past zero.
+        if ch.current_count != 0 {
+            ch.current_count -= 1;
+            return (paddr, false);
+        }
+        self.status[channel / 4] |= 1 << (channel % 4);
+        self.request &= !(1 << channel);
+        if ch.mode & MODE_AUTOINIT != 0 {
+            ch.current_address = ch.base_address;
+            ch.current_count = ch.base_count;
+        } else {
+            ch.current_count = 0xFFFF;
+            ch.masked = true;
+        }
+        (paddr, true)
+    }
+
+    /// Device-to-memory transfer (a DMA "write" cycle) pushed by a device.
+    ///
+    /// Units are written at the channel's current address until `data` is exhausted or the
+    /// channel reaches terminal count. Masked channels transfer nothing; on 16-bit channels a
+    /// trailing odd byte is not transferred.
+    pub fn transfer_to_memory(
+        &mut self,
+        channel: usize,
+        mem: &mut dyn MemoryBus,
+        data: &[u8],
+    ) -> DmaTransfer {
+        self.transfer(channel, data.len(), |paddr, range| {
+            mem.write_physical(paddr, &data[range]);
+        })
+    }
+
+    /// Memory-to-device transfer (a DMA "read" cycle) pulled by a device.
+    ///
+    /// Units are read from the channel's current address until `buf` is full or the channel
+    /// reaches terminal count. Masked channels transfer nothing; on 16-bit channels a trailing
+    /// odd byte is not transferred.
+    pub fn transfer_from_memory(
+        &mut self,
+        channel: usize,
+        mem: &mut dyn MemoryBus,
+        buf: &mut [u8],
+    ) -> DmaTransfer {
+        let len = buf.len();
+        self.transfer(channel, len, |paddr, range| {
+            mem.read_physical(paddr, &mut buf[range]);
+        })
+    }
+
+    fn transfer(
+        &mut self,
+        channel: usize,
+        len: usize,
+        mut cycle: impl FnMut(u64, std::ops::Range<usize>),
+    ) -> DmaTransfer {
+        let mut out = DmaTransfer::default();
+        if channel >= DMA_CHANNELS || !self.channel_ready(channel) {
+            return out;
+        }
+        let unit = Self::unit_len(channel);
+        let verify = self.channels[channel].mode & MODE_TRANSFER_MASK == MODE_TRANSFER_VERIFY;
+
+        while out.bytes + unit <= len {
+            let (paddr, tc) = self.step(channel);
+            if !verify {
+                cycle(paddr, out.bytes..out.bytes + unit);
+            }
+            out.bytes += unit;
+            if tc {
+                out.terminal_count = true;
+                break;
+            }
+        }
+        out
+    }
+
+    /// Run transfers for channels with a registered [`DmaChannelClient`] or a software request,
+    /// moving at most `max_units` units. Returns the number of units transferred.
+    ///
+    /// Channels are serviced in fixed priority order (0-3, then 5-7). Single mode releases the bus
+    /// after every unit, demand mode transfers while DREQ stays asserted, and block mode runs to
+    /// terminal count once started (carrying over to the next call if `max_units` runs out).
+    pub fn service(&mut self, mem: &mut dyn MemoryBus, max_units: usize) -> usize {
+        let mut done = 0;
+        while done < max_units {
+            let mut progressed = false;
+            for channel in 0..DMA_CHANNELS {
+                if done >= max_units {
+                    break;
+                }
+                if !self.channel_ready(channel) || !self.dreq(channel) {
+                    continue;
+                }
+                let units = self.service_channel(channel, mem, max_units - done);
+                progressed |= units != 0;
+                done += units;
+            }
+            if !progressed {
+                break;
+            }
+        }
+        done
+    }
+
+    fn service_channel(
+        &mut self,
+        channel: usize,
+        mem: &mut dyn MemoryBus,
+        max_units: usize,
+    ) -> usize {
+        let select = self.channels[channel].mode & MODE_SELECT_MASK;
+        if select == MODE_SELECT_BLOCK {
+            // Block mode only needs DREQ to start; hold the request until TC.
+            self.request |= 1 << channel;
+        }
+
+        let mut units = 0;
+        while units < max_units {
+            let tc = self.cycle(channel, mem);
+            units += 1;
+            if tc || select == MODE_SELECT_SINGLE {
+                break;
+            }
+            if select == MODE_SELECT_DEMAND && !self.dreq(channel) {
+                break;
+            }
+        }
+        units
+    }
+
+    /// Run one DACK cycle on `channel`, returning whether TC was reached.
+    fn cycle(&mut self, channel: usize, mem: &mut dyn MemoryBus) -> bool {
+        let mode = self.channels[channel].mode;
+        let (paddr, tc) = self.step(channel);
+        let client = self.clients[channel].clone();
+        let mut data = [0u8; 2];
+        let data = &mut data[..Self::unit_len(channel)];
+
+        match mode & MODE_TRANSFER_MASK {
+            MODE_TRANSFER_WRITE => {
+                if let Some(client) = &client {
+                    client.borrow_mut().dma_ack(DmaCycle::ToMemory(data));
+                }
+                mem.write_physical(paddr, data);
+            }
+            MODE_TRANSFER_READ => {
+                mem.read_physical(paddr, data);
+                if let Some(client) = &client {
+                    client.borrow_mut().dma_ack(DmaCycle::FromMemory(data));
+                }
+            }
+            // Verify (and the undefined transfer type 3) moves no data.
+            _ => {
+                if let Some(client) = &client {
+                    client.borrow_mut().dma_ack(DmaCycle::Verify);
+                }
+            }
+        }
+        if tc {
+            if let Some(client) = &client {
+                client.borrow_mut().dma_terminal_count();
+            }
+        }
+        tc
+    }
+}
+
+impl IoSnapshot for Dma8237 {
+    const DEVICE_ID: [u8; 4] = *b"DMA8";
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+
+    fn save_state(&self) -> Vec<u8> {
+        const TAG_REGS: u16 = 1;
+        const TAG_CHANNELS: u16 = 2;
+        const TAG_FLIP_FLOP: u16 = 3;
+        const TAG_STATUS: u16 = 4;
+        const TAG_CHANNELS_16: u16 = 5;
+        const TAG_FLIP_FLOP_16: u16 = 6;
+        const TAG_STATUS_16: u16 = 7;
+        const TAG_COMMAND: u16 = 8;
+        const TAG_REQUEST: u16 = 9;
+
+        let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
+
+        // Sort the latched register file so the encoding is deterministic.
+        let mut regs: Vec<(u16, u8)> = self.regs.iter().map(|(&p, &v)| (p, v)).collect();
+        regs.sort_unstable();
+        let mut enc = Encoder::new().u32(regs.len() as u32);
+        for (port, value) in regs {
+            enc = enc.u16(port).u8(value);
+        }
+        w.field_bytes(TAG_REGS, enc.finish());
+
+        let encode_channels = |channels: &[DmaChannel]| {
+            let mut enc = Encoder::new();
+            for ch in channels {
+                enc = enc
+                    .u16(ch.base_address)
+                    .u16(ch.current_address)
+                    .u16(ch.base_count)
+                    .u16(ch.current_count)
+                    .u8(ch.mode)
+                    .bool(ch.masked);
+            }
+            enc.finish()
+        };
+        // Channels 0-3 keep their original tag so older snapshots remain loadable.
+        w.field_bytes(
+            TAG_CHANNELS,
+            encode_channels(&self.channels[..DMA8_CHANNELS]),
+        );
+        w.field_bool(TAG_FLIP_FLOP, self.flip_flop[0]);
+        w.field_u8(TAG_STATUS, self.status[0]);
+        w.field_bytes(
+            TAG_CHANNELS_16,
+            encode_channels(&self.channels[DMA8_CHANNELS..]),
+        );
+        w.field_bool(TAG_FLIP_FLOP_16, self.flip_flop[1]);
+        w.field_u8(TAG_STATUS_16, self.status[1]);
+        w.field_bytes(
+            TAG_COMMAND,
+            Encoder::new()
+                .u8(self.command[0])
+                .u8(self.command[1])
+                .finish(),
+        );
+        w.field_u8(TAG_REQUEST, self.request);
+
+        // Registered clients are host wiring and are not serialized.
+        w.finish()
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
+        const TAG_REGS: u16 = 1;
+        const TAG_CHANNELS: u16 = 2;
+        const TAG_FLIP_FLOP: u16 = 3;
+        const TAG_STATUS: u16 = 4;
+        const TAG_CHANNELS_16: u16 = 5;
+        const TAG_FLIP_FLOP_16: u16 = 6;
+        const TAG_STATUS_16: u16 = 7;
+        const TAG_COMMAND: u16 = 8;
+        const TAG_REQUEST: u16 = 9;
+
+        let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
+        r.ensure_device_major(Self::DEVICE_VERSION.major)?;
+
+        self.reset();
+
+        if let Some(buf) = r.bytes(TAG_REGS) {
+            let mut d = Decoder::new(buf);
+            let count = d.u32()? as usize;
+            // Only 0x00..=0xDF is decoded by the controller; reject anything larger.
+            const MAX_SNAPSHOT_REGS: usize = 0x100;
+            if count > MAX_SNAPSHOT_REGS {
+                return Err(SnapshotError::InvalidFieldEncoding("dma regs"));
+            }
+            for _ in 0..count {
+                let port = d.u16()?;
+                let value = d.u8()?;
+                self.regs.insert(port, value);
+            }
+            d.finish()?;
+        }
+        let decode_channels = |buf: &[u8], channels: &mut [DmaChannel]| -> SnapshotResult<()> {
+            let mut d = Decoder::new(buf);
+            for ch in channels {
+                ch.base_address = d.u16()?;
+                ch.current_address = d.u16()?;
+                ch.base_count = d.u16()?;
+                ch.current_count = d.u16()?;
+                ch.mode = d.u8()?;
+                ch.masked = d.bool()?;
+            }
+            d.finish()
+        };
+        let (channels8, channels16) = self.channels.split_at_mut(DMA8_CHANNELS);
+        if let Some(buf) = r.bytes(TAG_CHANNELS) {
+            decode_channels(buf, channels8)?;
+        }
+        if let Some(buf) = r.bytes(TAG_CHANNELS_16) {
+            decode_channels(buf, channels16)?;
+        }
+        self.flip_flop = [
+            r.bool(TAG_FLIP_FLOP)?.unwrap_or(false),
+            r.bool(TAG_FLIP_FLOP_16)?.unwrap_or(false),
+        ];
+        self.status = [
+            r.u8(TAG_STATUS)?.unwrap_or(0) & 0x0F,
+            r.u8(TAG_STATUS_16)?.unwrap_or(0) & 0x0F,
+        ];
+        if let Some(buf) = r.bytes(TAG_COMMAND) {
+            let mut d = Decoder::new(buf);
+            self.command = [d.u8()?, d.u8()?];
+            d.finish()?;
+        }
+        self.request = r.u8(TAG_REQUEST)?.unwrap_or(0);
+        Ok(())
     }
 }
 
@@ -51,7 +725,7 @@ impl PortIoDevice for Dma8237Port {
             return 0;
         }
         debug_assert_eq!(port, self.port);
-        let dma = self.dma.borrow();
+        let mut dma = self.dma.borrow_mut();
         match size {
             1 => u32::from(dma.read_u8(port)),
             2 => {
@@ -97,6 +771,10 @@ impl PortIoDevice for Dma8237Port {
     fn reset(&mut self) {
         self.dma.borrow_mut().reset();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "dma8237"
+    }
 }
 
 pub fn register_dma8237(bus: &mut IoPortBus, dma: SharedDma8237) {
@@ -119,18 +797,270 @@ pub fn register_dma8237(bus: &mut IoPortBus, dma: SharedDma8237) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use memory::Bus;
 
     #[test]
     fn port_io_size0_is_noop() {
         let dma = Rc::new(RefCell::new(Dma8237::new()));
-        let mut port = Dma8237Port::new(dma.clone(), 0x00);
+        let mut port = Dma8237Port::new(dma.clone(), 0x81);
 
         // Size-0 writes must not latch values.
-        port.write(0x00, 0, 0x12);
-        assert_eq!(port.read(0x00, 1), 0);
+        port.write(0x81, 0, 0x12);
+        assert_eq!(port.read(0x81, 1), 0);
 
         // Sanity: size-1 writes should still work.
-        port.write(0x00, 1, 0x34);
-        assert_eq!(port.read(0x00, 1), 0x34);
+        port.write(0x81, 1, 0x34);
+        assert_eq!(port.read(0x81, 1), 0x34);
+    }
+
+    fn program_channel2(dma: &mut Dma8237, addr: u32, len: u16, mode: u8) {
+        dma.write_u8(PORT_SINGLE_MASK, 0x04 | 2);
+        dma.write_u8(PORT_CLEAR_FLIP_FLOP, 0);
+        dma.write_u8(PORT_MODE, mode | 2);
+        dma.write_u8(0x04, addr as u8);
+        dma.write_u8(0x04, (addr >> 8) as u8);
+        dma.write_u8(0x81, (addr >> 16) as u8);
+        dma.write_u8(0x05, (len - 1) as u8);
+        dma.write_u8(0x05, ((len - 1) >> 8) as u8);
+        dma.write_u8(PORT_SINGLE_MASK, 2);
+    }
+
+    #[test]
+    fn channel2_transfer_stops_at_terminal_count_and_masks_channel() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x20000);
+        // Single mode, increment, write-to-memory.
+        program_channel2(&mut dma, 0x1_2000, 4, 0x44);
+
+        let xfer = dma.transfer_to_memory(2, &mut mem, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            xfer,
+            DmaTransfer {
+                bytes: 4,
+                terminal_count: true
+            }
+        );
+        let mut buf = [0u8; 5];
+        mem.read_physical(0x1_2000, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4, 0]);
+
+        assert!(dma.channel_masked(2));
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND) & 0x0F, 1 << 2);
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND) & 0x0F, 0);
+
+        // Current address/count read back through the flip-flop.
+        dma.write_u8(PORT_CLEAR_FLIP_FLOP, 0);
+        assert_eq!(dma.read_u8(0x04), 0x04);
+        assert_eq!(dma.read_u8(0x04), 0x20);
+        assert_eq!(dma.read_u8(0x05), 0xFF);
+        assert_eq!(dma.read_u8(0x05), 0xFF);
+
+        assert_eq!(dma.transfer_to_memory(2, &mut mem, &[9]).bytes, 0);
+    }
+
+    /// Device that produces an incrementing byte pattern and records what it receives.
+    #[derive(Default)]
+    struct FakeClient {
+        dreq: bool,
+        next: u8,
+        received: Vec<u8>,
+        acks: usize,
+        terminal_counts: usize,
+    }
+
+    impl DmaChannelClient for FakeClient {
+        fn dma_request(&self) -> bool {
+            self.dreq
+        }
+
+        fn dma_ack(&mut self, cycle: DmaCycle<'_>) {
+            self.acks += 1;
+            match cycle {
+                DmaCycle::ToMemory(data) => {
+                    for byte in data {
+                        *byte = self.next;
+                        self.next = self.next.wrapping_add(1);
+                    }
+                }
+                DmaCycle::FromMemory(data) => self.received.extend_from_slice(data),
+                DmaCycle::Verify => {}
+            }
+        }
+
+        fn dma_terminal_count(&mut self) {
+            self.terminal_counts += 1;
+            self.dreq = false;
+        }
+    }
+
+    fn attach_client(dma: &mut Dma8237, channel: usize) -> Rc<RefCell<FakeClient>> {
+        let client = Rc::new(RefCell::new(FakeClient {
+            dreq: true,
+            next: 1,
+            ..Default::default()
+        }));
+        dma.register_client(channel, client.clone()).unwrap();
+        client
+    }
+
+    #[test]
+    fn single_mode_client_transfer_signals_terminal_count() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x20000);
+        let client = attach_client(&mut dma, 2);
+        // Single mode, increment, write-to-memory.
+        program_channel2(&mut dma, 0x1_FFFE, 4, 0x44);
+
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND), 1 << 6);
+        assert_eq!(dma.service(&mut mem, 64), 4);
+
+        // The address counter wraps within the 64 KiB page instead of carrying into the page.
+        let mut buf = [0u8; 2];
+        mem.read_physical(0x1_FFFE, &mut buf);
+        assert_eq!(buf, [1, 2]);
+        mem.read_physical(0x1_0000, &mut buf);
+        assert_eq!(buf, [3, 4]);
+
+        let client = client.borrow();
+        assert_eq!((client.acks, client.terminal_counts), (4, 1));
+        assert!(dma.channel_masked(2));
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND), 1 << 2);
+    }
+
+    #[test]
+    fn autoinit_block_transfer_reloads_and_repeats() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x10000);
+        mem.write_physical(0x4000, &[0x10, 0x20, 0x30]);
+        let client = attach_client(&mut dma, 1);
+
+        // Channel 1: block mode, autoinit, increment, read-from-memory, 3 bytes at 0x4000.
+        dma.write_u8(PORT_CLEAR_FLIP_FLOP, 0);
+        dma.write_u8(PORT_MODE, 0x98 | 1);
+        dma.write_u8(0x02, 0x00);
+        dma.write_u8(0x02, 0x40);
+        dma.write_u8(0x83, 0);
+        dma.write_u8(0x03, 2);
+        dma.write_u8(0x03, 0);
+        dma.write_u8(PORT_SINGLE_MASK, 1);
+
+        // The block runs to TC even though the client drops DREQ at TC; a budget cut carries over.
+        assert_eq!(dma.service(&mut mem, 2), 2);
+        assert_eq!(client.borrow().received, vec![0x10, 0x20]);
+        client.borrow_mut().dreq = false;
+        assert_eq!(dma.service(&mut mem, 64), 1);
+        assert_eq!(client.borrow().terminal_counts, 1);
+        assert!(!dma.channel_masked(1));
+
+        // Autoinit restored the base address and count: the next request repeats the block.
+        client.borrow_mut().dreq = true;
+        assert_eq!(dma.service(&mut mem, 64), 3);
+        let client = client.borrow();
+        assert_eq!(client.received, vec![0x10, 0x20, 0x30, 0x10, 0x20, 0x30]);
+        assert_eq!(client.terminal_counts, 2);
+    }
+
+    #[test]
+    fn verify_mode_counts_without_moving_data() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x10000);
+        let client = attach_client(&mut dma, 2);
+        // Single mode, verify.
+        program_channel2(&mut dma, 0x2000, 3, 0x40);
+
+        assert_eq!(dma.service(&mut mem, 64), 3);
+        let mut buf = [0u8; 3];
+        mem.read_physical(0x2000, &mut buf);
+        assert_eq!(buf, [0; 3]);
+        assert_eq!(client.borrow().acks, 3);
+        assert_eq!(client.borrow().terminal_counts, 1);
+    }
+
+    #[test]
+    fn sixteen_bit_channel_transfers_words() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x40000);
+        let client = attach_client(&mut dma, 5);
+
+        // Channel 5 (secondary controller): single mode, write-to-memory, 2 words at 0x2_1000.
+        dma.write_u8(0xD8, 0);
+        dma.write_u8(0xD6, 0x44 | 1);
+        let word_addr = (0x2_1000u32 >> 1) as u16;
+        dma.write_u8(0xC4, word_addr as u8);
+        dma.write_u8(0xC4, (word_addr >> 8) as u8);
+        dma.write_u8(0x8B, 0x02);
+        dma.write_u8(0xC6, 1);
+        dma.write_u8(0xC6, 0);
+        dma.write_u8(0xD4, 1);
+
+        assert_eq!(dma.service(&mut mem, 64), 2);
+        let mut buf = [0u8; 5];
+        mem.read_physical(0x2_1000, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4, 0]);
+        assert_eq!(client.borrow().terminal_counts, 1);
+        assert_eq!(dma.read_u8(0xD0) & 0x0F, 1 << 1);
+        assert!(dma.channel_masked(5));
+    }
+
+    #[test]
+    fn cascade_channel_rejects_clients_and_reset_keeps_registrations() {
+        let mut dma = Dma8237::new();
+        let client: SharedDmaChannelClient = Rc::new(RefCell::new(FakeClient::default()));
+        assert_eq!(
+            dma.register_client(DMA_CASCADE_CHANNEL, client.clone()),
+            Err(DmaClientError::CascadeChannel)
+        );
+        assert_eq!(
+            dma.register_client(8, client.clone()),
+            Err(DmaClientError::InvalidChannel(8))
+        );
+        dma.register_client(3, client.clone()).unwrap();
+        assert_eq!(
+            dma.register_client(3, client),
+            Err(DmaClientError::ChannelInUse(3))
+        );
+
+        dma.reset();
+        assert!(dma.unregister_client(3).is_some());
+    }
+
+    #[test]
+    fn software_request_and_controller_disable() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x10000);
+        // Block mode, write-to-memory, no client: the transfer writes zeros.
+        mem.write_physical(0x5000, &[0xFF; 2]);
+        program_channel2(&mut dma, 0x5000, 2, 0x84);
+
+        dma.write_u8(PORT_STATUS_COMMAND, COMMAND_DISABLE);
+        dma.write_u8(PORT_REQUEST, 0x04 | 2);
+        assert_eq!(dma.service(&mut mem, 64), 0);
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND), 1 << 6);
+
+        dma.write_u8(PORT_STATUS_COMMAND, 0);
+        assert_eq!(dma.service(&mut mem, 64), 2);
+        let mut buf = [0xAAu8; 2];
+        mem.read_physical(0x5000, &mut buf);
+        assert_eq!(buf, [0, 0]);
+        assert_eq!(dma.read_u8(PORT_STATUS_COMMAND), 1 << 2);
+    }
+
+    #[test]
+    fn snapshot_roundtrip_preserves_channel_programming() {
+        let mut dma = Dma8237::new();
+        let mut mem = Bus::new(0x10000);
+        mem.write_physical(0x3000, &[0xAA, 0xBB, 0xCC]);
+        // Single mode, increment, read-from-memory.
+        program_channel2(&mut dma, 0x3000, 3, 0x48);
+        let mut buf = [0u8; 1];
+        assert_eq!(dma.transfer_from_memory(2, &mut mem, &mut buf).bytes, 1);
+
+        let mut restored = Dma8237::new();
+        restored.load_state(&dma.save_state()).unwrap();
+        let mut rest = [0u8; 4];
+        let xfer = restored.transfer_from_memory(2, &mut mem, &mut rest);
+        assert_eq!(xfer.bytes, 2);
+        assert!(xfer.terminal_count);
+        assert_eq!(&rest[..2], &[0xBB, 0xCC]);
     }
 }
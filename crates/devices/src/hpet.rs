@@ -324,6 +324,35 @@ impl<C: Clock> Hpet<C> {
         self.service_timers(sink);
     }
 
+    /// Nanoseconds until the main counter reaches the comparator of an armed, interrupt-enabled
+    /// timer, or `None` if the HPET is disabled or no such timer exists.
+    pub fn ns_until_next_interrupt(&self) -> Option<u64> {
+        if !self.enabled() {
+            return None;
+        }
+        let period_fs = u128::from(self.config.capabilities.counter_clk_period_fs);
+        if period_fs == 0 {
+            return None;
+        }
+
+        let elapsed_ns = u128::from(self.clock.now_ns().wrapping_sub(self.last_update_ns));
+        self.timers
+            .iter()
+            .filter(|timer| timer.armed && timer.int_enabled())
+            .map(|timer| {
+                // Mirror `update_main_counter`: the comparator matches once
+                // `elapsed_ns * 1e6 + remainder_fs >= ticks * period_fs`.
+                let ticks = u128::from(timer.comparator.saturating_sub(self.main_counter));
+                let match_ns = (ticks * period_fs)
+                    .saturating_sub(u128::from(self.remainder_fs))
+                    .div_ceil(1_000_000);
+                match_ns
+                    .saturating_sub(elapsed_ns)
+                    .min(u128::from(u64::MAX)) as u64
+            })
+            .min()
+    }
+
     /// Synchronizes any pending level-triggered timer interrupts into the provided sink.
     ///
     /// This is primarily intended for snapshot restore flows: [`IoSnapshot::load_state()`]
@@ -834,6 +863,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ns_until_next_interrupt_reports_comparator_match() {
+        let clock = ManualClock::new();
+        let mut ioapic = IoApic::default();
+        let mut hpet = Hpet::new_default(clock.clone());
+
+        let timer0_cfg = hpet.mmio_read(REG_TIMER0_BASE + REG_TIMER_CONFIG, 8, &mut ioapic);
+        hpet.mmio_write(
+            REG_TIMER0_BASE + REG_TIMER_CONFIG,
+            8,
+            timer0_cfg | TIMER_CFG_INT_ENABLE,
+            &mut ioapic,
+        );
+        hpet.mmio_write(REG_TIMER0_BASE + REG_TIMER_COMPARATOR, 8, 5, &mut ioapic);
+        assert_eq!(hpet.ns_until_next_interrupt(), None);
+
+        hpet.mmio_write(REG_GENERAL_CONFIG, 8, GEN_CONF_ENABLE, &mut ioapic);
+        clock.advance_ns(150);
+        assert_eq!(hpet.ns_until_next_interrupt(), Some(350));
+
+        clock.advance_ns(349);
+        hpet.poll(&mut ioapic);
+        assert!(ioapic.take_events().is_empty());
+        clock.advance_ns(1);
+        hpet.poll(&mut ioapic);
+        assert_eq!(
+            ioapic.take_events(),
+            vec![GsiEvent::Raise(2), GsiEvent::Lower(2)]
+        );
+        assert_eq!(hpet.ns_until_next_interrupt(), None);
+    }
+
     #[test]
     fn interrupt_status_is_write_one_to_clear() {
         let clock = ManualClock::new();
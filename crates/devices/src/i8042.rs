@@ -118,6 +118,10 @@ impl PortIoDevice for I8042Port {
         // times (once per port mapping) as the operation is idempotent.
         self.inner.borrow_mut().reset();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "i8042"
+    }
 }
 
 /// Convenience helper to register the i8042 controller ports on an [`IoPortBus`].
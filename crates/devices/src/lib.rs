@@ -33,6 +33,7 @@ pub mod ioapic;
 pub mod irq;
 pub mod reset_ctrl;
 pub mod rtc_cmos;
+pub mod tpm;
 
 pub use pic8259::DualPic8259;
 pub use pit8254::Pit8254;
@@ -22,6 +22,8 @@ pub struct PciMappedBar {
 pub struct PciBus {
     devices: BTreeMap<PciBdf, Box<dyn PciDevice>>,
     mapped_bars: BTreeMap<(PciBdf, u8), PciBarRange>,
+    // Number of well-formed config writes (any BDF), for coarse guest-activity tracking.
+    config_writes: u64,
 }
 
 impl PciBus {
@@ -42,6 +44,14 @@ impl PciBus {
         self.devices.get_mut(&bdf).map(|dev| dev.config_mut())
     }
 
+    /// Number of well-formed config space writes seen since the bus was created.
+    ///
+    /// Counts writes from every access path (mechanism #1 ports, ECAM and firmware helpers),
+    /// including writes to absent functions. Not part of snapshots.
+    pub fn config_write_count(&self) -> u64 {
+        self.config_writes
+    }
+
     pub fn iter_device_addrs(&self) -> impl Iterator<Item = PciBdf> + '_ {
         self.devices.keys().copied()
     }
@@ -215,6 +225,7 @@ impl PciBus {
         {
             return;
         }
+        self.config_writes = self.config_writes.wrapping_add(1);
         let effects = {
             let Some(dev) = self.devices.get_mut(&bdf) else {
                 return;
@@ -75,6 +75,36 @@ impl GsiLevelSink for IoApicPicMirrorSink<'_> {
     }
 }
 
+/// Offset of the PIIX3 PIRQ route control registers (PIRQRC[A:D], one byte each) in the ISA
+/// bridge function's config space.
+pub const PIIX3_PIRQ_ROUTE_OFFSET: u16 = 0x60;
+
+/// PIRQRC bit 7: the PIRQ is not routed to any ISA IRQ.
+pub const PIIX3_PIRQ_ROUTE_DISABLE: u8 = 0x80;
+
+/// Decodes a PIIX3 PIRQRC register value into the ISA IRQ/GSI it routes to.
+///
+/// Returns `None` when routing is disabled or the IRQ field names a line PIIX3 cannot route to
+/// (IRQ0-2 and IRQ8). IRQ13 is accepted because the platform's default table routes PIRQD there.
+pub fn piix3_pirq_route_to_gsi(value: u8) -> Option<u32> {
+    if value & PIIX3_PIRQ_ROUTE_DISABLE != 0 {
+        return None;
+    }
+    match value & 0x0F {
+        0..=2 | 8 => None,
+        irq => Some(u32::from(irq)),
+    }
+}
+
+/// Encodes a PIRQ route as a PIIX3 PIRQRC register value (the inverse of
+/// [`piix3_pirq_route_to_gsi`]). GSIs outside the ISA range encode as disabled.
+pub fn piix3_pirq_route_from_gsi(gsi: Option<u32>) -> u8 {
+    match gsi.and_then(|gsi| u8::try_from(gsi).ok()) {
+        Some(irq) if irq < 16 => irq,
+        _ => PIIX3_PIRQ_ROUTE_DISABLE,
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct PciIntxRouterConfig {
     /// Mapping of PIRQ[A-D] to GSIs.
@@ -103,8 +133,13 @@ struct IntxSource {
 ///
 /// Each PIRQ[A-D] is then mapped to a platform GSI. Multiple devices can share a PIRQ and/or GSI,
 /// so the router maintains level-triggered semantics by reference-counting assertions.
+///
+/// The PIRQ-to-GSI mapping starts from [`PciIntxRouterConfig`] and can be reprogrammed at runtime
+/// with [`PciIntxRouter::set_pirq_route`] (e.g. from the chipset's PIRQ route registers).
 pub struct PciIntxRouter {
     cfg: PciIntxRouterConfig,
+    /// PIRQs whose routing has been disabled; their sources are tracked but drive no GSI.
+    pirq_disabled: [bool; 4],
     source_level: HashMap<IntxSource, bool>,
     gsi_assert_count: HashMap<u32, u32>,
 }
@@ -113,11 +148,70 @@ impl PciIntxRouter {
     pub fn new(cfg: PciIntxRouterConfig) -> Self {
         Self {
             cfg,
+            pirq_disabled: [false; 4],
             source_level: HashMap::new(),
             gsi_assert_count: HashMap::new(),
         }
     }
 
+    /// Returns the GSI PIRQ `pirq` (0 = A .. 3 = D) currently drives, or `None` if disabled.
+    pub fn pirq_route(&self, pirq: usize) -> Option<u32> {
+        (!self.pirq_disabled[pirq]).then_some(self.cfg.pirq_to_gsi[pirq])
+    }
+
+    /// Reroutes PIRQ `pirq` (0 = A .. 3 = D) to `gsi`, or disconnects it when `gsi` is `None`.
+    ///
+    /// Sources currently asserting INTx on the PIRQ release their old GSI and assert the new one,
+    /// so a level-triggered interrupt follows the line it was moved to.
+    pub fn set_pirq_route(&mut self, pirq: usize, gsi: Option<u32>, sink: &mut dyn GsiLevelSink) {
+        let old = self.pirq_route(pirq);
+        if old == gsi {
+            return;
+        }
+
+        let asserted = self
+            .source_level
+            .iter()
+            .filter(|(src, level)| **level && self.pirq_index(src.bdf, src.pin) == pirq)
+            .count();
+        if let Some(old) = old {
+            for _ in 0..asserted {
+                self.release_gsi(old, sink);
+            }
+        }
+
+        self.pirq_disabled[pirq] = gsi.is_none();
+        if let Some(gsi) = gsi {
+            self.cfg.pirq_to_gsi[pirq] = gsi;
+            for _ in 0..asserted {
+                self.acquire_gsi(gsi, sink);
+            }
+        }
+    }
+
+    fn routed_gsi(&self, bdf: PciBdf, pin: PciInterruptPin) -> Option<u32> {
+        self.pirq_route(self.pirq_index(bdf, pin))
+    }
+
+    fn acquire_gsi(&mut self, gsi: u32, sink: &mut dyn GsiLevelSink) {
+        let count = self.gsi_assert_count.entry(gsi).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            sink.set_gsi_level(gsi, true);
+        }
+    }
+
+    fn release_gsi(&mut self, gsi: u32, sink: &mut dyn GsiLevelSink) {
+        let count = self.gsi_assert_count.entry(gsi).or_insert(0);
+        debug_assert!(*count > 0, "INTx deassert would underflow assert count");
+        if *count > 0 {
+            *count -= 1;
+            if *count == 0 {
+                sink.set_gsi_level(gsi, false);
+            }
+        }
+    }
+
     /// Computes the PIRQ index (0 = A, 1 = B, 2 = C, 3 = D) for a device/pin pair.
     pub fn pirq_index(&self, bdf: PciBdf, pin: PciInterruptPin) -> usize {
         pci_routing::pirq_index(bdf.device, pin.index() as u8) as usize
@@ -171,22 +265,13 @@ impl PciIntxRouter {
             return;
         }
 
-        let gsi = self.gsi_for_intx(bdf, pin);
-        let count = self.gsi_assert_count.entry(gsi).or_insert(0);
-
+        let Some(gsi) = self.routed_gsi(bdf, pin) else {
+            return;
+        };
         if level {
-            *count += 1;
-            if *count == 1 {
-                sink.set_gsi_level(gsi, true);
-            }
+            self.acquire_gsi(gsi, sink);
         } else {
-            debug_assert!(*count > 0, "INTx deassert would underflow assert count");
-            if *count > 0 {
-                *count -= 1;
-                if *count == 0 {
-                    sink.set_gsi_level(gsi, false);
-                }
-            }
+            self.release_gsi(gsi, sink);
         }
     }
 
@@ -211,7 +296,7 @@ impl PciIntxRouter {
     /// controller to ensure routed GSIs reflect the restored state.
     pub fn sync_levels_to_sink(&self, sink: &mut dyn GsiLevelSink) {
         let mut seen = BTreeSet::new();
-        for gsi in self.cfg.pirq_to_gsi {
+        for gsi in (0..4).filter_map(|pirq| self.pirq_route(pirq)) {
             if !seen.insert(gsi) {
                 continue;
             }
@@ -228,6 +313,7 @@ impl IoSnapshot for PciIntxRouter {
     fn save_state(&self) -> Vec<u8> {
         const TAG_CFG: u16 = 1;
         const TAG_SOURCES: u16 = 2;
+        const TAG_PIRQ_DISABLED: u16 = 3;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -263,12 +349,22 @@ impl IoSnapshot for PciIntxRouter {
         }
         w.field_bytes(TAG_SOURCES, enc.finish());
 
+        let disabled = self
+            .pirq_disabled
+            .iter()
+            .enumerate()
+            .fold(0u8, |acc, (pirq, &disabled)| {
+                acc | (u8::from(disabled) << pirq)
+            });
+        w.field_u8(TAG_PIRQ_DISABLED, disabled);
+
         w.finish()
     }
 
     fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
         const TAG_CFG: u16 = 1;
         const TAG_SOURCES: u16 = 2;
+        const TAG_PIRQ_DISABLED: u16 = 3;
         const MAX_INTX_SOURCES: usize = 256 * 32 * 8 * 4;
 
         let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
@@ -279,6 +375,10 @@ impl IoSnapshot for PciIntxRouter {
             self.cfg.pirq_to_gsi = [d.u32()?, d.u32()?, d.u32()?, d.u32()?];
             d.finish()?;
         }
+        let disabled = r.u8(TAG_PIRQ_DISABLED)?.unwrap_or(0);
+        for (pirq, slot) in self.pirq_disabled.iter_mut().enumerate() {
+            *slot = disabled & (1 << pirq) != 0;
+        }
 
         self.source_level.clear();
         self.gsi_assert_count.clear();
@@ -320,8 +420,9 @@ impl IoSnapshot for PciIntxRouter {
             if !*level {
                 continue;
             }
-            let gsi = self.gsi_for_intx(src.bdf, src.pin);
-            *self.gsi_assert_count.entry(gsi).or_insert(0) += 1;
+            if let Some(gsi) = self.routed_gsi(src.bdf, src.pin) {
+                *self.gsi_assert_count.entry(gsi).or_insert(0) += 1;
+            }
         }
 
         Ok(())
@@ -573,6 +674,71 @@ mod tests {
         assert_eq!(sink.events, vec![(10, true), (10, false)]);
     }
 
+    #[test]
+    fn rerouting_pirq_moves_asserted_level_to_new_gsi() {
+        let mut router = PciIntxRouter::new(PciIntxRouterConfig::default());
+        let mut sink = MockSink::default();
+
+        let dev0 = PciBdf::new(0, 0, 0);
+        let dev1 = PciBdf::new(0, 1, 0); // INTA -> PIRQB (GSI 11).
+
+        router.assert_intx(dev0, PciInterruptPin::IntA, &mut sink);
+        router.assert_intx(dev1, PciInterruptPin::IntA, &mut sink);
+        sink.events.clear();
+
+        // PIRQA joins PIRQB on GSI 11, which is already asserted.
+        router.set_pirq_route(0, Some(11), &mut sink);
+        assert_eq!(router.pirq_route(0), Some(11));
+        assert_eq!(sink.events, vec![(10, false)]);
+
+        // GSI 11 stays high until both sharers deassert.
+        router.deassert_intx(dev1, PciInterruptPin::IntA, &mut sink);
+        assert_eq!(sink.events, vec![(10, false)]);
+        router.deassert_intx(dev0, PciInterruptPin::IntA, &mut sink);
+        assert_eq!(sink.events, vec![(10, false), (11, false)]);
+    }
+
+    #[test]
+    fn disabled_pirq_drops_and_ignores_intx() {
+        let mut router = PciIntxRouter::new(PciIntxRouterConfig::default());
+        let mut sink = MockSink::default();
+        let dev0 = PciBdf::new(0, 0, 0);
+
+        router.assert_intx(dev0, PciInterruptPin::IntA, &mut sink);
+        router.set_pirq_route(0, None, &mut sink);
+        assert_eq!(router.pirq_route(0), None);
+        assert_eq!(sink.events, vec![(10, true), (10, false)]);
+
+        router.deassert_intx(dev0, PciInterruptPin::IntA, &mut sink);
+        router.assert_intx(dev0, PciInterruptPin::IntA, &mut sink);
+        assert_eq!(sink.events.len(), 2);
+
+        // Re-enabling picks up the level the source is still driving.
+        router.set_pirq_route(0, Some(5), &mut sink);
+        assert_eq!(sink.events[2..], [(5, true)]);
+
+        // The disabled mask survives a snapshot round trip.
+        router.set_pirq_route(3, None, &mut sink);
+        let mut restored = PciIntxRouter::new(PciIntxRouterConfig::default());
+        restored.load_state(&router.save_state()).unwrap();
+        assert_eq!(restored.pirq_route(0), Some(5));
+        assert_eq!(restored.pirq_route(3), None);
+    }
+
+    #[test]
+    fn piix3_pirq_route_register_encoding() {
+        assert_eq!(piix3_pirq_route_to_gsi(0x0B), Some(11));
+        assert_eq!(piix3_pirq_route_to_gsi(0x8B), None);
+        assert_eq!(piix3_pirq_route_to_gsi(0x02), None);
+        assert_eq!(piix3_pirq_route_to_gsi(0x08), None);
+        assert_eq!(piix3_pirq_route_from_gsi(Some(10)), 0x0A);
+        assert_eq!(
+            piix3_pirq_route_from_gsi(Some(42)),
+            PIIX3_PIRQ_ROUTE_DISABLE
+        );
+        assert_eq!(piix3_pirq_route_from_gsi(None), PIIX3_PIRQ_ROUTE_DISABLE);
+    }
+
     #[test]
     fn configure_device_updates_interrupt_line_and_pin_registers() {
         let router = PciIntxRouter::new(PciIntxRouterConfig::default());
@@ -29,7 +29,9 @@ pub use config::{
 pub use config_synced_mmio_bar::PciConfigSyncedMmioBar;
 pub use ecam::{PciEcamConfig, PciEcamMmio, PCIE_ECAM_BUS_STRIDE};
 pub use irq_router::{
-    GsiLevelSink, IoApicPicMirrorSink, PciIntxRouter, PciIntxRouterConfig, PicIrqLevelSink,
+    piix3_pirq_route_from_gsi, piix3_pirq_route_to_gsi, GsiLevelSink, IoApicPicMirrorSink,
+    PciIntxRouter, PciIntxRouterConfig, PicIrqLevelSink, PIIX3_PIRQ_ROUTE_DISABLE,
+    PIIX3_PIRQ_ROUTE_OFFSET,
 };
 pub use msi::MsiCapability;
 pub use msix::MsixCapability;
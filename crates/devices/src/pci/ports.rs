@@ -2,7 +2,7 @@ use super::{PciBus, PciBusSnapshot, PciConfigMechanism1, PciPlatform};
 use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
-use aero_platform::io::{IoPortBus, PortIoDevice};
+use aero_platform::io::{AccessPolicy, AccessSizes, IoPortBus, PortIoDevice};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -121,6 +121,10 @@ impl PortIoDevice for PciConfigPort {
         // multiple times (once per port mapping) as the operation is idempotent.
         self.cfg.borrow_mut().reset_io_state();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "pci-config"
+    }
 }
 
 /// Range-mapped PCI config mechanism #1 ports.
@@ -152,13 +156,22 @@ impl PortIoDevice for PciConfigPortRange {
         // multiple times and does not affect the PCI bus topology.
         self.cfg.borrow_mut().reset_io_state();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "pci-config"
+    }
 }
 
+/// Register the config mechanism #1 ports as one range device.
+///
+/// Accesses are split into naturally aligned pieces, so each piece stays within the address or
+/// data dword; a piece straddling past `0xCFF` is dispatched to whatever decodes the next port.
 pub fn register_pci_config_ports(bus: &mut IoPortBus, cfg: SharedPciConfigPorts) {
     const PCI_CFG_PORTS_LEN: u16 = (PCI_CFG_DATA_PORT + 4) - PCI_CFG_ADDR_PORT;
-    bus.register_range(
+    bus.register_range_with_policy(
         PCI_CFG_ADDR_PORT,
         PCI_CFG_PORTS_LEN,
+        AccessPolicy::Split(AccessSizes::BYTE | AccessSizes::WORD | AccessSizes::DWORD),
         Box::new(PciConfigPortRange::new(cfg)),
     );
 }
@@ -227,4 +240,38 @@ mod tests {
         let expected_bar1_mask = (!(BAR1_SIZE - 1) & 0xFFFF_FFFC) | 0x1;
         assert_eq!(bar1_mask, expected_bar1_mask);
     }
+
+    #[test]
+    fn registered_cfg_ports_split_accesses_straddling_dwords_and_the_range_end() {
+        struct TestDev {
+            cfg: PciConfigSpace,
+        }
+
+        impl PciDevice for TestDev {
+            fn config(&self) -> &PciConfigSpace {
+                &self.cfg
+            }
+
+            fn config_mut(&mut self) -> &mut PciConfigSpace {
+                &mut self.cfg
+            }
+        }
+
+        let cfg_ports = Rc::new(RefCell::new(PciConfigPorts::new()));
+        cfg_ports.borrow_mut().bus_mut().add_device(
+            PciBdf::new(0, 1, 0),
+            Box::new(TestDev {
+                cfg: PciConfigSpace::new(0x1234, 0x5678),
+            }),
+        );
+        let mut bus = IoPortBus::new();
+        register_pci_config_ports(&mut bus, cfg_ports);
+
+        bus.write(0xCF8, 4, 0x8000_0000 | (1 << 11));
+        // Address byte 3 (enable bit) followed by the vendor ID low byte.
+        assert_eq!(bus.read(0xCFB, 2), 0x3480);
+        // Device ID, then two unclaimed ports past the window.
+        assert_eq!(bus.read(0xCFE, 4), 0xFFFF_5678);
+        assert_eq!(bus.read(0xCFD, 4), 0xFF56_7812);
+    }
 }
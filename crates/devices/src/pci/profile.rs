@@ -283,6 +283,9 @@ pub const PCI_DEVICE_ID_VIRTIO_INPUT_TRANSITIONAL: u16 = 0x1011;
 pub const PCI_DEVICE_ID_VIRTIO_SND_TRANSITIONAL: u16 = 0x1018;
 pub const PCI_DEVICE_ID_VIRTIO_NET_MODERN: u16 = 0x1041;
 pub const PCI_DEVICE_ID_VIRTIO_BLK_MODERN: u16 = 0x1042;
+pub const PCI_DEVICE_ID_VIRTIO_RNG_MODERN: u16 = 0x1044;
+pub const PCI_DEVICE_ID_VIRTIO_BALLOON_MODERN: u16 = 0x1045;
+pub const PCI_DEVICE_ID_VIRTIO_9P_MODERN: u16 = 0x1049;
 pub const PCI_DEVICE_ID_VIRTIO_INPUT_MODERN: u16 = 0x1052;
 pub const PCI_DEVICE_ID_VIRTIO_SND_MODERN: u16 = 0x1059;
 
@@ -471,6 +474,17 @@ pub const VIRTIO_BARS: [PciBarProfile; 1] = [PciBarProfile::mem64(
     false,
 )];
 
+/// PCI BAR index of the legacy (virtio 0.9) I/O register block exposed by transitional devices.
+pub const VIRTIO_LEGACY_IO_BAR_INDEX: u8 = 2;
+/// Size in bytes of the legacy virtio-pci I/O register block (BAR2).
+pub const VIRTIO_LEGACY_IO_BAR_SIZE: u64 = 0x100;
+
+/// BARs of a transitional virtio-pci device: the modern BAR0 plus the legacy I/O BAR2.
+pub const VIRTIO_TRANSITIONAL_BARS: [PciBarProfile; 2] = [
+    VIRTIO_BARS[0],
+    PciBarProfile::io(VIRTIO_LEGACY_IO_BAR_INDEX, VIRTIO_LEGACY_IO_BAR_SIZE),
+];
+
 /// AeroGPU BAR0 index (MMIO control registers).
 pub const AEROGPU_BAR0_INDEX: u8 = 0;
 /// AeroGPU BAR1 index (prefetchable VRAM aperture).
@@ -485,6 +499,33 @@ pub const AEROGPU_BAR0_SIZE: u64 = 64 * 1024;
 /// `docs/16-aerogpu-vga-vesa-compat.md` for the intended BAR1 layout and the VBE LFB offset rules.
 pub const AEROGPU_VRAM_SIZE: u64 = 64 * 1024 * 1024;
 
+// -----------------------------------------------------------------------------
+// AeroGPU MSI-X configuration (BAR0-backed MSI-X table + PBA)
+// -----------------------------------------------------------------------------
+//
+// AeroGPU exposes one MSI-X vector per interrupt source so the KMD can use message-signaled
+// interrupts instead of the shared legacy INTx line (`AEROGPU_FEATURE_MSIX`). Vector assignment
+// is part of the driver ABI (`AEROGPU_MSIX_VECTOR_*` in `drivers/aerogpu/protocol/aerogpu_pci.h`):
+// 0 = fence, 1 = vblank, 2 = error.
+//
+// Layout (mirrors `AEROGPU_PCI_BAR0_MSIX_{TABLE,PBA}_OFFSET_BYTES`):
+// - Table: BAR0 + 0x8000, three 16-byte entries
+// - PBA:   BAR0 + 0x9000, 8 bytes (bits 0..=2)
+pub const AEROGPU_MSIX_TABLE_SIZE: u16 = 3;
+pub const AEROGPU_MSIX_TABLE_BAR: u8 = AEROGPU_BAR0_INDEX;
+pub const AEROGPU_MSIX_TABLE_OFFSET: u32 = 0x8000;
+pub const AEROGPU_MSIX_PBA_BAR: u8 = AEROGPU_BAR0_INDEX;
+pub const AEROGPU_MSIX_PBA_OFFSET: u32 = 0x9000;
+
+/// Canonical capabilities exposed by the AeroGPU profile.
+pub const AEROGPU_CAPS: [PciCapabilityProfile; 1] = [PciCapabilityProfile::Msix {
+    table_size: AEROGPU_MSIX_TABLE_SIZE,
+    table_bar: AEROGPU_MSIX_TABLE_BAR,
+    table_offset: AEROGPU_MSIX_TABLE_OFFSET,
+    pba_bar: AEROGPU_MSIX_PBA_BAR,
+    pba_offset: AEROGPU_MSIX_PBA_OFFSET,
+}];
+
 pub const AEROGPU_BARS: [PciBarProfile; 2] = [
     // BAR0: 64KiB non-prefetchable MMIO registers.
     PciBarProfile::mem32(AEROGPU_BAR0_INDEX, AEROGPU_BAR0_SIZE, false),
@@ -692,6 +733,19 @@ pub const fn virtio_msix_capability_profile_for_table_size(
     }
 }
 
+/// Turns a modern virtio-pci profile into its transitional variant.
+///
+/// Transitional devices use the `0x1000 + type - 1` device ID range (modern IDs are
+/// `0x1040 + type`) and additionally expose the legacy I/O BAR, so both legacy and modern drivers
+/// can bind.
+pub const fn virtio_transitional_profile(profile: PciDeviceProfile) -> PciDeviceProfile {
+    PciDeviceProfile {
+        device_id: profile.device_id - 0x1040 + 0x1000 - 1,
+        bars: &VIRTIO_TRANSITIONAL_BARS,
+        ..profile
+    }
+}
+
 pub const VIRTIO_NET_CAPS: [PciCapabilityProfile; 5] = [
     VIRTIO_VENDOR_CAPS[0],
     VIRTIO_VENDOR_CAPS[1],
@@ -710,6 +764,33 @@ pub const VIRTIO_BLK_CAPS: [PciCapabilityProfile; 5] = [
     virtio_msix_capability_profile_for_table_size(2),
 ];
 
+pub const VIRTIO_BALLOON_CAPS: [PciCapabilityProfile; 5] = [
+    VIRTIO_VENDOR_CAPS[0],
+    VIRTIO_VENDOR_CAPS[1],
+    VIRTIO_VENDOR_CAPS[2],
+    VIRTIO_VENDOR_CAPS[3],
+    // virtio-balloon has 3 virtqueues (inflate/deflate/stats) + 1 config vector.
+    virtio_msix_capability_profile_for_table_size(4),
+];
+
+pub const VIRTIO_RNG_CAPS: [PciCapabilityProfile; 5] = [
+    VIRTIO_VENDOR_CAPS[0],
+    VIRTIO_VENDOR_CAPS[1],
+    VIRTIO_VENDOR_CAPS[2],
+    VIRTIO_VENDOR_CAPS[3],
+    // virtio-rng has 1 virtqueue (requests) + 1 config vector.
+    virtio_msix_capability_profile_for_table_size(2),
+];
+
+pub const VIRTIO_9P_CAPS: [PciCapabilityProfile; 5] = [
+    VIRTIO_VENDOR_CAPS[0],
+    VIRTIO_VENDOR_CAPS[1],
+    VIRTIO_VENDOR_CAPS[2],
+    VIRTIO_VENDOR_CAPS[3],
+    // virtio-9p has 1 virtqueue (requests) + 1 config vector.
+    virtio_msix_capability_profile_for_table_size(2),
+];
+
 pub const VIRTIO_INPUT_CAPS: [PciCapabilityProfile; 5] = [
     VIRTIO_VENDOR_CAPS[0],
     VIRTIO_VENDOR_CAPS[1],
@@ -947,7 +1028,8 @@ pub const VGA_TRANSITIONAL_STUB: PciDeviceProfile = PciDeviceProfile {
 ///
 /// In the canonical `aero_machine::Machine`, `MachineConfig::enable_aerogpu=true` wires an MVP
 /// device model behind this identity:
-/// - BAR0: AeroGPU MMIO registers (ring + doorbell + fence + scanout/cursor register surface)
+/// - BAR0: AeroGPU MMIO registers (ring + doorbell + fence + scanout/cursor register surface),
+///   plus the MSI-X table/PBA at `0x8000`/`0x9000` (see [`AEROGPU_CAPS`])
 /// - BAR1: a host-backed VRAM aperture:
 ///   - the legacy VGA window aliases into `VRAM[0..0x20000)` (128KiB)
 ///   - the first 256KiB is reserved for legacy VGA planar storage (4 × 64KiB planes)
@@ -977,7 +1059,7 @@ pub const AEROGPU: PciDeviceProfile = PciDeviceProfile {
     header_type: 0x00,
     interrupt_pin: Some(PciInterruptPin::IntA),
     bars: &AEROGPU_BARS,
-    capabilities: &[],
+    capabilities: &AEROGPU_CAPS,
 };
 
 pub const VIRTIO_NET: PciDeviceProfile = PciDeviceProfile {
@@ -1091,6 +1173,60 @@ pub const VIRTIO_SND: PciDeviceProfile = PciDeviceProfile {
     capabilities: &VIRTIO_SND_CAPS,
 };
 
+/// Optional virtio memory balloon.
+///
+/// Not part of [`CANONICAL_IO_DEVICES`]: it is only present when the platform enables it.
+pub const VIRTIO_BALLOON: PciDeviceProfile = PciDeviceProfile {
+    name: "virtio-balloon",
+    bdf: PciBdf::new(0, 0x0e, 0),
+    vendor_id: PCI_VENDOR_ID_VIRTIO,
+    device_id: PCI_DEVICE_ID_VIRTIO_BALLOON_MODERN,
+    subsystem_vendor_id: PCI_VENDOR_ID_VIRTIO,
+    subsystem_id: 5,
+    revision_id: 1,
+    class: PciClassCode::new(0xff, 0x00, 0x00),
+    header_type: 0x00,
+    interrupt_pin: Some(PciInterruptPin::IntA),
+    bars: &VIRTIO_BARS,
+    capabilities: &VIRTIO_BALLOON_CAPS,
+};
+
+/// Optional virtio-9p host folder share.
+///
+/// Not part of [`CANONICAL_IO_DEVICES`]: it is only present when the platform enables it.
+pub const VIRTIO_9P: PciDeviceProfile = PciDeviceProfile {
+    name: "virtio-9p",
+    bdf: PciBdf::new(0, 0x0f, 0),
+    vendor_id: PCI_VENDOR_ID_VIRTIO,
+    device_id: PCI_DEVICE_ID_VIRTIO_9P_MODERN,
+    subsystem_vendor_id: PCI_VENDOR_ID_VIRTIO,
+    subsystem_id: 9,
+    revision_id: 1,
+    class: PciClassCode::new(0x01, 0x80, 0x00),
+    header_type: 0x00,
+    interrupt_pin: Some(PciInterruptPin::IntA),
+    bars: &VIRTIO_BARS,
+    capabilities: &VIRTIO_9P_CAPS,
+};
+
+/// Optional virtio entropy device.
+///
+/// Not part of [`CANONICAL_IO_DEVICES`]: it is only present when the platform enables it.
+pub const VIRTIO_RNG: PciDeviceProfile = PciDeviceProfile {
+    name: "virtio-rng",
+    bdf: PciBdf::new(0, 0x10, 0),
+    vendor_id: PCI_VENDOR_ID_VIRTIO,
+    device_id: PCI_DEVICE_ID_VIRTIO_RNG_MODERN,
+    subsystem_vendor_id: PCI_VENDOR_ID_VIRTIO,
+    subsystem_id: 4,
+    revision_id: 1,
+    class: PciClassCode::new(0xff, 0x00, 0x00),
+    header_type: 0x00,
+    interrupt_pin: Some(PciInterruptPin::IntA),
+    bars: &VIRTIO_BARS,
+    capabilities: &VIRTIO_RNG_CAPS,
+};
+
 pub const CANONICAL_IO_DEVICES: &[PciDeviceProfile] = &[
     ISA_PIIX3,
     IDE_PIIX3,
@@ -1256,4 +1392,30 @@ mod tests {
         assert_eq!(EHCI_BARS[0].size, 0x1000);
         assert!(!EHCI_BARS[0].prefetchable);
     }
+
+    #[test]
+    fn virtio_transitional_profile_uses_legacy_ids_and_io_bar() {
+        assert_eq!(
+            virtio_transitional_profile(VIRTIO_NET).device_id,
+            PCI_DEVICE_ID_VIRTIO_NET_TRANSITIONAL
+        );
+        assert_eq!(
+            virtio_transitional_profile(VIRTIO_BLK).device_id,
+            PCI_DEVICE_ID_VIRTIO_BLK_TRANSITIONAL
+        );
+        assert_eq!(
+            virtio_transitional_profile(VIRTIO_INPUT_KEYBOARD).device_id,
+            PCI_DEVICE_ID_VIRTIO_INPUT_TRANSITIONAL
+        );
+
+        let cfg = virtio_transitional_profile(VIRTIO_BLK).build_config_space();
+        let bar2 = cfg.bar_definition(VIRTIO_LEGACY_IO_BAR_INDEX);
+        assert_eq!(
+            bar2,
+            Some(PciBarDefinition::Io {
+                size: VIRTIO_LEGACY_IO_BAR_SIZE as u32
+            })
+        );
+        assert!(cfg.bar_definition(VIRTIO_BAR0_INDEX).is_some());
+    }
 }
@@ -77,6 +77,10 @@ impl PortIoDevice for Pic8259Port {
             _ => pic.port_write_u8(port, value as u8),
         }
     }
+
+    fn debug_name(&self) -> &'static str {
+        "pic8259"
+    }
 }
 
 /// Convenience helper to register a dual PIC on an [`IoPortBus`].
@@ -168,6 +172,10 @@ pub fn register_pic8259_on_platform_interrupts(
                 _ => pic.port_write_u8(port, value as u8),
             }
         }
+
+        fn debug_name(&self) -> &'static str {
+            "pic8259"
+        }
     }
 
     bus.register(
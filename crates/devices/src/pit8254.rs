@@ -3,11 +3,13 @@
 //! This implementation focuses on the subset required for PC BIOS / OS bringup:
 //! - Channels 0-2 on ports 0x40-0x43.
 //! - Channel 0 modes 2 (rate generator) and 3 (square wave).
+//! - Channel 2 gating and PC speaker output via system control port B (0x61).
 //! - Lobyte/hibyte sequencing, count latching, and a simplified read-back command.
 //!
 //! Timing is deterministic: time progresses only via [`Pit8254::advance_ns`], which
 //! converts nanoseconds into PIT input clock ticks (1.193182 MHz).
 
+use crate::clock::TimerCatchupPolicy;
 use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
 use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
@@ -16,18 +18,48 @@ use aero_platform::interrupts::{InterruptInput, PlatformInterrupts};
 use aero_platform::io::{IoPortBus, PortIoDevice};
 use core::fmt;
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::rc::Rc;
 
 pub const PIT_CH0: u16 = 0x40;
 pub const PIT_CH1: u16 = 0x41;
 pub const PIT_CH2: u16 = 0x42;
 pub const PIT_CMD: u16 = 0x43;
+/// System control port B: channel 2 gate, speaker enable and timer 2 output.
+pub const PIT_PORT_B: u16 = 0x61;
 
 /// PIT input clock frequency (Hz).
 pub const PIT_HZ: u64 = 1_193_182;
 
 const NS_PER_SEC: u128 = 1_000_000_000;
 
+/// Maximum number of completed [`SpeakerEvent`]s buffered until [`Pit8254::take_speaker_events`].
+/// Older events are dropped first.
+pub const MAX_SPEAKER_EVENTS: usize = 256;
+
+const PORT_B_GATE2: u8 = 1 << 0;
+const PORT_B_SPEAKER_DATA: u8 = 1 << 1;
+/// Bits 2-3 (parity / channel check enables) are latched but otherwise ignored.
+const PORT_B_WRITABLE_MASK: u8 = 0x0F;
+const PORT_B_REFRESH: u8 = 1 << 4;
+const PORT_B_OUT2: u8 = 1 << 5;
+/// The DRAM refresh request bit toggles every ~15.085us on PC/AT-compatible chipsets.
+const REFRESH_TOGGLE_NS: u64 = 15_085;
+
+/// A tone played on the PC speaker, in [`Pit8254::advance_ns`] guest time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeakerEvent {
+    pub start_ns: u64,
+    pub duration_ns: u64,
+    pub frequency_hz: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SpeakerTone {
+    start_ns: u64,
+    frequency_hz: u32,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum AccessMode {
     LatchCount,
@@ -275,6 +307,22 @@ impl Channel {
         }
     }
 
+    /// Input clock ticks until [`Channel::advance_ticks`] reports the next pulse, if any.
+    fn ticks_until_pulse(&self) -> Option<u64> {
+        let reload = self.effective_reload()?;
+        match self.mode {
+            2 | 3 => {
+                let reload = if self.mode == 3 && reload == 1 {
+                    2
+                } else {
+                    reload
+                };
+                Some(u64::from(reload - self.phase_ticks))
+            }
+            _ => None,
+        }
+    }
+
     fn read_data(&mut self) -> u8 {
         if let Some(status) = self.latched_status.take() {
             self.read_phase = BytePhase::Low;
@@ -355,8 +403,20 @@ impl Channel {
 pub struct Pit8254 {
     channels: [Channel; 3],
     ns_remainder: u128,
+    /// Total nanoseconds passed to [`Pit8254::advance_ns`] since reset.
+    elapsed_ns: u64,
+    /// Input clock ticks advanced since reset (diagnostic; not snapshotted).
+    elapsed_ticks: u64,
     irq0_pulses: u64,
+    /// IRQ0 pulses dropped by `catchup` since reset.
+    irq0_coalesced_pulses: u64,
     irq0_callback: Option<Box<dyn FnMut() + 'static>>,
+    /// Host policy for large time steps; `None` delivers every pulse.
+    catchup: Option<TimerCatchupPolicy>,
+    /// Writable bits of system control port B.
+    port_b: u8,
+    speaker_tone: Option<SpeakerTone>,
+    speaker_events: VecDeque<SpeakerEvent>,
 }
 
 impl Pit8254 {
@@ -371,8 +431,32 @@ impl Pit8254 {
     /// [`Pit8254::connect_irq0`] / [`Pit8254::connect_irq0_to_platform_interrupts`].
     pub fn reset(&mut self) {
         let irq0_callback = self.irq0_callback.take();
+        let catchup = self.catchup;
         *self = Self::default();
         self.irq0_callback = irq0_callback;
+        self.catchup = catchup;
+    }
+
+    /// Set the catch-up policy applied when one advance covers many channel 0 periods (see
+    /// [`TimerCatchupPolicy`]). `None` (the default) delivers every pulse.
+    ///
+    /// Like the IRQ0 wiring this is host configuration: it survives [`Pit8254::reset`] and snapshot
+    /// restore and is not serialized.
+    pub fn set_catchup_policy(&mut self, policy: Option<TimerCatchupPolicy>) {
+        self.catchup = policy;
+    }
+
+    /// Number of IRQ0 pulses dropped by the catch-up policy since reset.
+    pub fn irq0_coalesced_pulses(&self) -> u64 {
+        self.irq0_coalesced_pulses
+    }
+
+    /// Number of PIT input clock ticks ([`PIT_HZ`]) advanced since reset.
+    ///
+    /// This is a host-side diagnostic for checking the ns → tick conversion against other
+    /// timebases; it is not part of snapshots.
+    pub fn elapsed_ticks(&self) -> u64 {
+        self.elapsed_ticks
     }
 
     /// Connect a callback that will be invoked once per IRQ0 pulse.
@@ -405,21 +489,45 @@ impl Pit8254 {
         pulses
     }
 
+    /// Drain completed PC speaker tones.
+    ///
+    /// A tone starts when channel 2 runs in square-wave mode with both the gate and speaker data
+    /// bits of port B set, and completes when any of those (or the frequency) changes. A tone still
+    /// playing is reported once it completes.
+    pub fn take_speaker_events(&mut self) -> Vec<SpeakerEvent> {
+        self.speaker_events.drain(..).collect()
+    }
+
     /// Advance the PIT's timebase by `ns` nanoseconds.
     pub fn advance_ns(&mut self, ns: u64) {
+        self.elapsed_ns = self.elapsed_ns.saturating_add(ns);
         let total = self.ns_remainder + (ns as u128) * (PIT_HZ as u128);
         let ticks = total / NS_PER_SEC;
         self.ns_remainder = total % NS_PER_SEC;
         self.advance_ticks(ticks as u64);
     }
 
+    /// Nanoseconds of [`Pit8254::advance_ns`] until the next IRQ0 pulse, or `None` if channel 0
+    /// is not generating pulses (unprogrammed or in a non-periodic mode).
+    pub fn ns_until_next_interrupt(&self) -> Option<u64> {
+        let ticks = self.channels[0].ticks_until_pulse()?;
+        // Smallest `ns` with `(ns_remainder + ns * PIT_HZ) / NS_PER_SEC >= ticks`.
+        let needed = (u128::from(ticks) * NS_PER_SEC).saturating_sub(self.ns_remainder);
+        Some(needed.div_ceil(u128::from(PIT_HZ)) as u64)
+    }
+
     /// Advance by a number of PIT input clock ticks.
     pub fn advance_ticks(&mut self, ticks: u64) {
         if ticks == 0 {
             return;
         }
+        self.elapsed_ticks = self.elapsed_ticks.saturating_add(ticks);
 
-        let pulses = self.channels[0].advance_ticks(ticks);
+        let due = self.channels[0].advance_ticks(ticks);
+        // The channel phase above already reflects every elapsed tick; only the number of pulses
+        // signalled on IRQ0 is bounded by the catch-up policy.
+        let pulses = self.catchup.map_or(due, |policy| policy.deliverable(due));
+        self.irq0_coalesced_pulses = self.irq0_coalesced_pulses.saturating_add(due - pulses);
         if pulses != 0 {
             self.irq0_pulses = self.irq0_pulses.saturating_add(pulses);
             if let Some(cb) = self.irq0_callback.as_mut() {
@@ -429,10 +537,73 @@ impl Pit8254 {
             }
         }
 
-        // Channels 1 and 2 may be stubbed; we still advance them so that reads return
-        // sensible values when guest code expects them to count.
+        // Channel 1 may be stubbed; we still advance it so that reads return sensible values
+        // when guest code expects it to count. Channel 2 only counts while its gate is high.
         self.channels[1].advance_ticks(ticks);
-        self.channels[2].advance_ticks(ticks);
+        if self.port_b & PORT_B_GATE2 != 0 {
+            self.channels[2].advance_ticks(ticks);
+        }
+    }
+
+    fn read_port_b(&self) -> u8 {
+        let mut val = self.port_b;
+        if (self.elapsed_ns / REFRESH_TOGGLE_NS) & 1 != 0 {
+            val |= PORT_B_REFRESH;
+        }
+        // With the gate low, channel 2 holds OUT high in the modes we model.
+        if self.port_b & PORT_B_GATE2 == 0 || self.channels[2].current_out() {
+            val |= PORT_B_OUT2;
+        }
+        val
+    }
+
+    fn write_port_b(&mut self, val: u8) {
+        let gate_rising = self.port_b & PORT_B_GATE2 == 0 && val & PORT_B_GATE2 != 0;
+        self.port_b = val & PORT_B_WRITABLE_MASK;
+        if gate_rising {
+            // A rising gate edge restarts the count in modes 2 and 3.
+            self.channels[2].phase_ticks = 0;
+        }
+        self.update_speaker();
+    }
+
+    fn speaker_frequency_hz(&self) -> Option<u32> {
+        let speaker_on = PORT_B_GATE2 | PORT_B_SPEAKER_DATA;
+        if self.port_b & speaker_on != speaker_on {
+            return None;
+        }
+        let ch = &self.channels[2];
+        if ch.mode != 3 || ch.null_count {
+            return None;
+        }
+        let reload = u64::from(ch.effective_reload()?.max(2));
+        Some(((PIT_HZ + reload / 2) / reload) as u32)
+    }
+
+    /// Close the current speaker tone and/or open a new one if the speaker output changed.
+    fn update_speaker(&mut self) {
+        let frequency_hz = self.speaker_frequency_hz();
+        if self.speaker_tone.map(|tone| tone.frequency_hz) == frequency_hz {
+            return;
+        }
+
+        if let Some(tone) = self.speaker_tone.take() {
+            let duration_ns = self.elapsed_ns - tone.start_ns;
+            if duration_ns != 0 {
+                if self.speaker_events.len() >= MAX_SPEAKER_EVENTS {
+                    self.speaker_events.pop_front();
+                }
+                self.speaker_events.push_back(SpeakerEvent {
+                    start_ns: tone.start_ns,
+                    duration_ns,
+                    frequency_hz: tone.frequency_hz,
+                });
+            }
+        }
+        self.speaker_tone = frequency_hz.map(|frequency_hz| SpeakerTone {
+            start_ns: self.elapsed_ns,
+            frequency_hz,
+        });
     }
 
     /// Read from an I/O port.
@@ -485,6 +656,7 @@ impl Pit8254 {
             PIT_CH1 => self.channels[1].read_data(),
             PIT_CH2 => self.channels[2].read_data(),
             PIT_CMD => 0,
+            PIT_PORT_B => self.read_port_b(),
             _ => 0xFF,
         }
     }
@@ -493,8 +665,15 @@ impl Pit8254 {
         match port {
             PIT_CH0 => self.channels[0].write_data(val),
             PIT_CH1 => self.channels[1].write_data(val),
-            PIT_CH2 => self.channels[2].write_data(val),
-            PIT_CMD => self.write_control(val),
+            PIT_CH2 => {
+                self.channels[2].write_data(val);
+                self.update_speaker();
+            }
+            PIT_CMD => {
+                self.write_control(val);
+                self.update_speaker();
+            }
+            PIT_PORT_B => self.write_port_b(val),
             _ => {}
         }
     }
@@ -541,6 +720,9 @@ impl IoSnapshot for Pit8254 {
         const TAG_NS_REMAINDER: u16 = 1;
         const TAG_IRQ0_PULSES: u16 = 2;
         const TAG_CHANNELS: u16 = 3;
+        const TAG_ELAPSED_NS: u16 = 4;
+        const TAG_PORT_B: u16 = 5;
+        const TAG_SPEAKER_TONE: u16 = 6;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
         // `ns_remainder` is always < 1e9, but keep it as u64 for clarity.
@@ -587,7 +769,20 @@ impl IoSnapshot for Pit8254 {
         }
         w.field_bytes(TAG_CHANNELS, enc.finish());
 
-        // `irq0_callback` is a host wiring detail; it is intentionally not serialized.
+        w.field_u64(TAG_ELAPSED_NS, self.elapsed_ns);
+        w.field_u8(TAG_PORT_B, self.port_b);
+        if let Some(tone) = self.speaker_tone {
+            w.field_bytes(
+                TAG_SPEAKER_TONE,
+                Encoder::new()
+                    .u64(tone.start_ns)
+                    .u32(tone.frequency_hz)
+                    .finish(),
+            );
+        }
+
+        // `irq0_callback` and `catchup` are host configuration, and completed speaker events are
+        // host output waiting to be drained; none of them is serialized.
         w.finish()
     }
 
@@ -595,14 +790,19 @@ impl IoSnapshot for Pit8254 {
         const TAG_NS_REMAINDER: u16 = 1;
         const TAG_IRQ0_PULSES: u16 = 2;
         const TAG_CHANNELS: u16 = 3;
+        const TAG_ELAPSED_NS: u16 = 4;
+        const TAG_PORT_B: u16 = 5;
+        const TAG_SPEAKER_TONE: u16 = 6;
 
         let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
         r.ensure_device_major(Self::DEVICE_VERSION.major)?;
 
         // Preserve host wiring while resetting to a deterministic baseline.
         let irq0_callback = self.irq0_callback.take();
+        let catchup = self.catchup;
         *self = Self::default();
         self.irq0_callback = irq0_callback;
+        self.catchup = catchup;
 
         if let Some(ns_rem) = r.u64(TAG_NS_REMAINDER)? {
             self.ns_remainder = ns_rem as u128;
@@ -671,6 +871,23 @@ impl IoSnapshot for Pit8254 {
             d.finish()?;
         }
 
+        if let Some(elapsed_ns) = r.u64(TAG_ELAPSED_NS)? {
+            self.elapsed_ns = elapsed_ns;
+        }
+        if let Some(port_b) = r.u8(TAG_PORT_B)? {
+            self.port_b = port_b & PORT_B_WRITABLE_MASK;
+        }
+        if let Some(buf) = r.bytes(TAG_SPEAKER_TONE) {
+            let mut d = Decoder::new(buf);
+            let start_ns = d.u64()?;
+            let frequency_hz = d.u32()?;
+            d.finish()?;
+            self.speaker_tone = Some(SpeakerTone {
+                start_ns: start_ns.min(self.elapsed_ns),
+                frequency_hz,
+            });
+        }
+
         Ok(())
     }
 }
@@ -679,9 +896,9 @@ pub type SharedPit8254 = Rc<RefCell<Pit8254>>;
 
 /// I/O-port view of a shared [`Pit8254`].
 ///
-/// `IoPortBus` maps one port to one device instance. A PIT responds to four ports,
-/// so the common pattern is to share the PIT behind `Rc<RefCell<_>>` and register
-/// four `Pit8254Port` instances.
+/// `IoPortBus` maps one port to one device instance. A PIT responds to four ports
+/// (plus port B for channel 2 gating), so the common pattern is to share the PIT behind
+/// `Rc<RefCell<_>>` and register one `Pit8254Port` instance per port.
 pub struct Pit8254Port {
     pit: SharedPit8254,
     port: u16,
@@ -709,6 +926,10 @@ impl PortIoDevice for Pit8254Port {
         // (once per port mapping) as the operation is idempotent.
         self.pit.borrow_mut().reset();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "pit8254"
+    }
 }
 
 /// Convenience helper to register the PIT ports on an [`IoPortBus`].
@@ -716,7 +937,8 @@ pub fn register_pit8254(bus: &mut IoPortBus, pit: SharedPit8254) {
     bus.register(PIT_CH0, Box::new(Pit8254Port::new(pit.clone(), PIT_CH0)));
     bus.register(PIT_CH1, Box::new(Pit8254Port::new(pit.clone(), PIT_CH1)));
     bus.register(PIT_CH2, Box::new(Pit8254Port::new(pit.clone(), PIT_CH2)));
-    bus.register(PIT_CMD, Box::new(Pit8254Port::new(pit, PIT_CMD)));
+    bus.register(PIT_CMD, Box::new(Pit8254Port::new(pit.clone(), PIT_CMD)));
+    bus.register(PIT_PORT_B, Box::new(Pit8254Port::new(pit, PIT_PORT_B)));
 }
 
 #[cfg(test)]
@@ -758,6 +980,101 @@ mod tests {
         assert_eq!(pit.take_irq0_pulses(), 2);
     }
 
+    #[test]
+    fn catchup_policy_bounds_pulses_but_keeps_phase() {
+        let mut pit = Pit8254::new();
+        pit.set_catchup_policy(Some(TimerCatchupPolicy {
+            max_missed_periods: 4,
+            max_burst: 2,
+        }));
+        program_divisor(&mut pit, 0x34, 10);
+
+        // Up to the threshold every period is delivered.
+        pit.advance_ticks(40);
+        assert_eq!(pit.take_irq0_pulses(), 4);
+        assert_eq!(pit.irq0_coalesced_pulses(), 0);
+
+        // 1003 ticks cover 100 periods: two pulses, the rest dropped, phase 3 ticks in.
+        pit.advance_ticks(1003);
+        assert_eq!(pit.take_irq0_pulses(), 2);
+        assert_eq!(pit.irq0_coalesced_pulses(), 98);
+        pit.advance_ticks(6);
+        assert_eq!(pit.take_irq0_pulses(), 0);
+        pit.advance_ticks(1);
+        assert_eq!(pit.take_irq0_pulses(), 1);
+
+        // The policy is host configuration and survives reset.
+        pit.reset();
+        program_divisor(&mut pit, 0x34, 10);
+        pit.advance_ticks(1000);
+        assert_eq!(pit.take_irq0_pulses(), 2);
+
+        // Strict mode delivers everything.
+        pit.set_catchup_policy(None);
+        pit.advance_ticks(1000);
+        assert_eq!(pit.take_irq0_pulses(), 100);
+    }
+
+    #[test]
+    fn ns_until_next_interrupt_matches_pulse_time() {
+        let mut pit = Pit8254::new();
+        assert_eq!(pit.ns_until_next_interrupt(), None);
+
+        // ch0, lobyte/hibyte, mode2, divisor 1000 (~838us).
+        program_divisor(&mut pit, 0x34, 1000);
+        pit.advance_ns(123_456);
+        assert_eq!(pit.take_irq0_pulses(), 0);
+
+        let ns = pit.ns_until_next_interrupt().unwrap();
+        pit.advance_ns(ns - 1);
+        assert_eq!(pit.take_irq0_pulses(), 0);
+        pit.advance_ns(1);
+        assert_eq!(pit.take_irq0_pulses(), 1);
+    }
+
+    #[test]
+    fn port_b_out2_follows_channel2_square_wave_while_gated() {
+        let mut pit = Pit8254::new();
+        // ch2, lobyte/hibyte, mode3, divisor 4: OUT high for 2 ticks, low for 2 ticks.
+        pit.port_write(PIT_CMD, 1, 0xB6);
+        pit.port_write(PIT_CH2, 1, 4);
+        pit.port_write(PIT_CH2, 1, 0);
+
+        // Gate low: the counter is held and OUT stays high.
+        pit.advance_ticks(3);
+        assert_ne!(pit.port_read(PIT_PORT_B, 1) as u8 & PORT_B_OUT2, 0);
+
+        pit.port_write(PIT_PORT_B, 1, u32::from(PORT_B_GATE2));
+        pit.advance_ticks(2);
+        assert_eq!(pit.port_read(PIT_PORT_B, 1) as u8 & PORT_B_OUT2, 0);
+        pit.advance_ticks(2);
+        assert_ne!(pit.port_read(PIT_PORT_B, 1) as u8 & PORT_B_OUT2, 0);
+        assert_eq!(
+            pit.port_read(PIT_PORT_B, 1) as u8 & PORT_B_WRITABLE_MASK,
+            PORT_B_GATE2
+        );
+    }
+
+    #[test]
+    fn speaker_events_are_bounded() {
+        let mut pit = Pit8254::new();
+        pit.port_write(PIT_CMD, 1, 0xB6);
+        pit.port_write(PIT_CH2, 1, 0xA9);
+        pit.port_write(PIT_CH2, 1, 0x04);
+
+        for _ in 0..MAX_SPEAKER_EVENTS + 10 {
+            pit.port_write(PIT_PORT_B, 1, 0x03);
+            pit.advance_ns(1_000);
+            pit.port_write(PIT_PORT_B, 1, 0x00);
+            pit.advance_ns(1_000);
+        }
+
+        let events = pit.take_speaker_events();
+        assert_eq!(events.len(), MAX_SPEAKER_EVENTS);
+        assert_eq!(events[0].start_ns, 10 * 2_000);
+        assert!(pit.take_speaker_events().is_empty());
+    }
+
     #[test]
     fn lobyte_hibyte_sequencing_gates_start() {
         let mut pit = Pit8254::new();
@@ -78,6 +78,10 @@ impl PortIoDevice for ResetCtrl {
     fn reset(&mut self) {
         self.value = 0;
     }
+
+    fn debug_name(&self) -> &'static str {
+        "reset-ctrl"
+    }
 }
 
 #[cfg(test)]
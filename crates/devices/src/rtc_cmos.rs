@@ -28,6 +28,23 @@ const REG_STATUS_B: u8 = 0x0B;
 const REG_STATUS_C: u8 = 0x0C;
 const REG_STATUS_D: u8 = 0x0D;
 
+/// CMOS floppy drive type code for a 3.5" 1.44MB drive (register `0x10`).
+pub const CMOS_FLOPPY_TYPE_1_44M: u8 = 0x4;
+
+/// CMOS shutdown status byte (register `0x0F`), written by software before a warm reset to tell
+/// firmware how to resume.
+pub const CMOS_SHUTDOWN_STATUS: u8 = 0x0F;
+
+/// First byte of the general-purpose CMOS RAM bank, past the clock, status, diagnostic and
+/// shutdown registers.
+pub const CMOS_NVRAM_START: u8 = 0x10;
+
+/// Size of the CMOS RAM bank starting at [`CMOS_NVRAM_START`] (`0x10..=0x7F`).
+pub const CMOS_NVRAM_LEN: usize = CMOS_LEN - CMOS_NVRAM_START as usize;
+
+const REG_FLOPPY_TYPES: u8 = 0x10;
+const REG_EQUIPMENT: u8 = 0x14;
+
 const REG_BASE_MEM_LO: u8 = 0x15;
 const REG_BASE_MEM_HI: u8 = 0x16;
 const REG_EXT_MEM_LO: u8 = 0x17;
@@ -39,6 +56,10 @@ const REG_HIGH_MEM_HI: u8 = 0x35;
 
 const REG_CENTURY: u8 = 0x32;
 
+// Standard AT checksum: 16-bit sum of `0x10..=0x2D`, stored big-endian in `0x2E`/`0x2F`.
+const REG_CHECKSUM_HI: u8 = 0x2E;
+const REG_CHECKSUM_LO: u8 = 0x2F;
+
 const REG_B_SET: u8 = 1 << 7;
 const REG_B_PIE: u8 = 1 << 6;
 const REG_B_AIE: u8 = 1 << 5;
@@ -93,6 +114,8 @@ pub struct RtcCmos<C: Clock, I: IrqLine> {
     last_rtc_seconds: i64,
     periodic_interval_ns: Option<u128>,
     next_periodic_ns: Option<u128>,
+    /// Periodic interrupt periods elapsed since reset (diagnostic; not snapshotted).
+    periodic_ticks: u64,
     irq_level: bool,
 }
 
@@ -129,6 +152,7 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
             last_rtc_seconds: 0,
             periodic_interval_ns: None,
             next_periodic_ns: None,
+            periodic_ticks: 0,
             irq_level: false,
         };
 
@@ -157,6 +181,7 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
         self.last_rtc_seconds = 0;
         self.periodic_interval_ns = None;
         self.next_periodic_ns = None;
+        self.periodic_ticks = 0;
         self.irq_level = false;
 
         // Deassert the IRQ line so the platform interrupt controller doesn't get stuck with a
@@ -195,6 +220,56 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
         self.irq_level
     }
 
+    /// Periodic interrupt rate selected by register A, or `None` while register B's PIE bit is
+    /// clear (or the rate select disables the divider output).
+    pub fn periodic_frequency_hz(&self) -> Option<u32> {
+        if self.reg_b & REG_B_PIE == 0 {
+            return None;
+        }
+        let rs = self.reg_a & 0x0F;
+        if rs < 3 {
+            return None;
+        }
+        Some(32_768u32 >> (rs - 1))
+    }
+
+    /// Number of periodic interrupt periods that have elapsed since reset, including periods
+    /// coalesced into a single PF flag by a large time step.
+    ///
+    /// This is a host-side diagnostic for checking the periodic timebase against other clocks; it
+    /// is not part of snapshots.
+    pub fn periodic_ticks(&self) -> u64 {
+        self.periodic_ticks
+    }
+
+    /// Nanoseconds until the RTC next sets an interrupt flag enabled in register B (periodic,
+    /// update-ended or alarm), or `None` if no RTC interrupt source is enabled.
+    ///
+    /// Alarms are only matched on update cycles, so an enabled alarm reports the next update even
+    /// if it will not match; the result is never later than the actual event.
+    pub fn ns_until_next_interrupt(&self) -> Option<u64> {
+        const NS_PER_SEC: u64 = 1_000_000_000;
+
+        let now_ns = self.clock.now_ns();
+        let periodic = self.periodic_interval_ns.map(|interval_ns| {
+            let next_ns = self
+                .next_periodic_ns
+                .unwrap_or(u128::from(now_ns) + interval_ns);
+            next_ns
+                .saturating_sub(u128::from(now_ns))
+                .min(u128::from(u64::MAX)) as u64
+        });
+        let update = (!self.set_mode && self.reg_b & (REG_B_UIE | REG_B_AIE) != 0).then(|| {
+            if self.rtc_seconds_at(now_ns) != self.last_rtc_seconds {
+                // A second boundary has passed that `tick` has not observed yet.
+                return 0;
+            }
+            let adjusted = now_ns.wrapping_add(u64::from(self.phase_offset_ns));
+            NS_PER_SEC - adjusted % NS_PER_SEC
+        });
+        periodic.into_iter().chain(update).min()
+    }
+
     fn init_nvram(&mut self) {
         self.set_memory_size_bytes(0);
     }
@@ -227,6 +302,79 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
         );
     }
 
+    /// Report floppy drives in CMOS (`0x10` drive types, `0x14` equipment byte).
+    ///
+    /// Drive types use the standard CMOS encoding (`0x4` = 3.5" 1.44MB); `0` means "no drive".
+    pub fn set_floppy_drive_types(&mut self, drive0: u8, drive1: u8) {
+        let (drive0, drive1) = (drive0 & 0x0F, drive1 & 0x0F);
+        self.nvram[REG_FLOPPY_TYPES as usize] = (drive0 << 4) | drive1;
+
+        let count = u8::from(drive0 != 0) + u8::from(drive1 != 0);
+        let mut equipment = self.nvram[REG_EQUIPMENT as usize] & !0xC1;
+        if count != 0 {
+            // Bit 0: floppy drives installed; bits 6-7: drive count - 1.
+            equipment |= 0x01 | ((count - 1) << 6);
+        }
+        self.nvram[REG_EQUIPMENT as usize] = equipment;
+    }
+
+    /// Set the RTC date/time to the wall-clock time `unix_ms` (milliseconds since the Unix epoch),
+    /// e.g. when resuming a snapshot taken long ago.
+    ///
+    /// Only the RTC's offset from its clock changes (including the sub-second phase, so the next
+    /// update happens on the wall-clock second boundary); the clock itself and the periodic
+    /// interrupt schedule are untouched. Date/time registers are encoded on read, so they follow
+    /// the guest's BCD/binary and 12/24h selection. Like a regular update cycle, this raises the
+    /// update-ended (and matching alarm) interrupt if enabled, unless the guest has halted updates
+    /// via `SET`.
+    pub fn set_wall_clock_unix_ms(&mut self, unix_ms: i64) {
+        const NS_PER_SEC: u64 = 1_000_000_000;
+
+        let now_ns = self.clock.now_ns();
+        let target_subsec_ns = unix_ms.rem_euclid(1000) as u64 * 1_000_000;
+        self.phase_offset_ns =
+            ((target_subsec_ns + NS_PER_SEC - now_ns % NS_PER_SEC) % NS_PER_SEC) as u32;
+        let unix_seconds = unix_ms.div_euclid(1000);
+        self.set_rtc_seconds(now_ns, unix_seconds);
+        if !self.set_mode {
+            self.handle_second_edge(unix_seconds);
+        }
+        self.update_irq_line();
+    }
+
+    /// Contents of the CMOS RAM bank (`0x10..=0x7F`), e.g. to persist guest settings across
+    /// machine instances.
+    pub fn nvram_bank(&self) -> [u8; CMOS_NVRAM_LEN] {
+        let mut bank = [0; CMOS_NVRAM_LEN];
+        bank.copy_from_slice(&self.nvram[usize::from(CMOS_NVRAM_START)..]);
+        bank
+    }
+
+    /// Replace the CMOS RAM bank (`0x10..=0x7F`) with previously saved contents.
+    ///
+    /// Firmware-owned fields (memory sizes, floppy types, checksum) are taken as-is; callers
+    /// re-apply them afterwards ([`RtcCmos::set_memory_size_bytes`],
+    /// [`RtcCmos::set_floppy_drive_types`], [`RtcCmos::update_checksum`]).
+    pub fn load_nvram_bank(&mut self, bank: &[u8; CMOS_NVRAM_LEN]) {
+        self.nvram[usize::from(CMOS_NVRAM_START)..].copy_from_slice(bank);
+    }
+
+    /// Recompute the standard checksum over `0x10..=0x2D` into `0x2E`/`0x2F`, as firmware does
+    /// after updating its configuration bytes.
+    pub fn update_checksum(&mut self) {
+        let sum = self.nvram[usize::from(CMOS_NVRAM_START)..usize::from(REG_CHECKSUM_HI)]
+            .iter()
+            .fold(0u16, |sum, &b| sum.wrapping_add(u16::from(b)));
+        self.nvram[usize::from(REG_CHECKSUM_HI)] = (sum >> 8) as u8;
+        self.nvram[usize::from(REG_CHECKSUM_LO)] = sum as u8;
+    }
+
+    /// Read and clear the shutdown status byte ([`CMOS_SHUTDOWN_STATUS`]), as firmware does early
+    /// in its reset path so a later reset runs a normal POST.
+    pub fn take_shutdown_status(&mut self) -> u8 {
+        std::mem::take(&mut self.nvram[CMOS_SHUTDOWN_STATUS as usize])
+    }
+
     fn tick_at(&mut self, now_ns: u64) {
         self.handle_periodic(now_ns as u128);
 
@@ -257,6 +405,7 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
         let elapsed = now_ns - *next_ns;
         let missed = elapsed / interval_ns + 1;
         *next_ns = next_ns.saturating_add(missed * interval_ns);
+        self.periodic_ticks = self.periodic_ticks.saturating_add(missed as u64);
     }
 
     fn handle_second_edge(&mut self, rtc_seconds: i64) {
@@ -384,21 +533,12 @@ impl<C: Clock, I: IrqLine> RtcCmos<C, I> {
     }
 
     fn recompute_periodic(&mut self, now_ns: u128) {
-        if self.reg_b & REG_B_PIE == 0 {
-            self.periodic_interval_ns = None;
-            self.next_periodic_ns = None;
-            return;
-        }
-
-        let rs = self.reg_a & 0x0F;
-        if rs < 3 {
+        let Some(freq_hz) = self.periodic_frequency_hz() else {
             self.periodic_interval_ns = None;
             self.next_periodic_ns = None;
             return;
-        }
+        };
 
-        let shift = rs.saturating_sub(1);
-        let freq_hz = 32_768u32 >> shift;
         let interval_ns = (1_000_000_000u128 / freq_hz as u128).max(1);
 
         self.periodic_interval_ns = Some(interval_ns);
@@ -589,6 +729,10 @@ impl<C: Clock, I: IrqLine> PortIoDevice for RtcCmos<C, I> {
             _ => {}
         }
     }
+
+    fn debug_name(&self) -> &'static str {
+        "rtc-cmos"
+    }
 }
 
 impl<C: Clock, I: IrqLine> IoSnapshot for RtcCmos<C, I> {
@@ -777,6 +921,10 @@ impl<C: Clock + 'static, I: IrqLine + 'static> PortIoDevice for RtcCmosPort<C, I
         // (once per port mapping) as the operation is idempotent.
         self.rtc.borrow_mut().reset();
     }
+
+    fn debug_name(&self) -> &'static str {
+        "rtc-cmos"
+    }
 }
 
 /// Convenience helper to register the RTC ports on an [`IoPortBus`].
@@ -993,6 +1141,33 @@ mod tests {
         assert_eq!(c2, 0);
     }
 
+    #[test]
+    fn wall_clock_resync_sets_time_and_keeps_clock() {
+        let clock = ManualClock::new();
+        clock.set_ns(3_400_000_000);
+        let irq = TestIrq::new();
+        let mut rtc = RtcCmos::new(clock.clone(), irq.clone());
+        write_reg(&mut rtc, REG_STATUS_B, REG_B_UIE);
+
+        // 2024-03-15 13:45:30.750 UTC, read back in 12-hour BCD mode.
+        rtc.set_wall_clock_unix_ms(1_710_510_330_750);
+        assert_eq!(clock.now_ns(), 3_400_000_000);
+        assert!(irq.level());
+        assert_eq!(read_reg(&mut rtc, REG_SECONDS), 0x30);
+        assert_eq!(read_reg(&mut rtc, REG_MINUTES), 0x45);
+        assert_eq!(read_reg(&mut rtc, REG_HOURS), 0x80 | 0x01);
+        assert_eq!(read_reg(&mut rtc, REG_DAY_OF_MONTH), 0x15);
+        assert_eq!(read_reg(&mut rtc, REG_MONTH), 0x03);
+        assert_eq!(read_reg(&mut rtc, REG_YEAR), 0x24);
+        assert_eq!(read_reg(&mut rtc, REG_CENTURY), 0x20);
+
+        // The sub-second phase follows the wall clock: the next update is 250ms away.
+        clock.advance_ns(240_000_000);
+        assert_eq!(read_reg(&mut rtc, REG_SECONDS), 0x30);
+        clock.advance_ns(20_000_000);
+        assert_eq!(read_reg(&mut rtc, REG_SECONDS), 0x31);
+    }
+
     #[test]
     fn irq8_asserts_only_when_enabled_and_event_occurs() {
         let clock = ManualClock::new();
@@ -1161,4 +1336,30 @@ mod tests {
         let high_hi = read_reg(&mut rtc, REG_HIGH_MEM_HI);
         assert_eq!(u16::from_le_bytes([high_lo, high_hi]), 256);
     }
+
+    #[test]
+    fn nvram_bank_round_trips_and_checksum_covers_config_bytes() {
+        let mut rtc = RtcCmos::new(ManualClock::new(), TestIrq::new());
+        write_reg(&mut rtc, 0x40, 0xA5);
+        write_reg(&mut rtc, 0x7F, 0x5A);
+        let bank = rtc.nvram_bank();
+        assert_eq!(bank[0x40 - usize::from(CMOS_NVRAM_START)], 0xA5);
+
+        let mut restored = RtcCmos::new(ManualClock::new(), TestIrq::new());
+        restored.load_nvram_bank(&bank);
+        assert_eq!(read_reg(&mut restored, 0x40), 0xA5);
+        assert_eq!(read_reg(&mut restored, 0x7F), 0x5A);
+        // The clock registers are not part of the bank.
+        assert_eq!(read_reg(&mut restored, REG_STATUS_B), REG_B_24H);
+
+        restored.set_floppy_drive_types(CMOS_FLOPPY_TYPE_1_44M, 0);
+        restored.update_checksum();
+        let expected: u16 = (0x10..0x2E)
+            .map(|idx| u16::from(read_reg(&mut restored, idx)))
+            .sum();
+        let hi = read_reg(&mut restored, REG_CHECKSUM_HI);
+        let lo = read_reg(&mut restored, REG_CHECKSUM_LO);
+        assert_eq!(u16::from_be_bytes([hi, lo]), expected);
+        assert_ne!(expected, 0);
+    }
 }
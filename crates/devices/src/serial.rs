@@ -6,11 +6,21 @@ use std::rc::Rc;
 /// Minimal 16550 UART.
 ///
 /// This is primarily for early boot debugging (BIOS logging / kernel serial
-/// console). Interrupt-driven operation is not implemented yet.
+/// console) and polled kernel debugging (Windows KDCOM). Transmission is instantaneous, so THR is
+/// always empty; the receive FIFO is unbounded (host-side input is never dropped).
+///
+/// Modelled behavior that polled and interrupt-driven drivers rely on:
+/// - LSR data-ready / THR-empty / transmitter-empty bits.
+/// - Received-data, character-timeout and THR-empty interrupt identification, honoring the FCR
+///   receive trigger level when FIFOs are enabled.
+/// - MCR loopback mode (used by port-presence probes), with MSR mirroring the MCR outputs.
+/// - Outside loopback, MSR reports a connected peer (CTS, DSR and DCD asserted).
 #[derive(Debug)]
 pub struct Serial16550 {
     base: u16,
     ier: u8,
+    /// THR-empty interrupt pending (cleared by reading IIR while it is reported, or writing THR).
+    thre_pending: bool,
     fcr: u8,
     lcr: u8,
     mcr: u8,
@@ -28,6 +38,7 @@ impl Serial16550 {
         Self {
             base,
             ier: 0,
+            thre_pending: false,
             fcr: 0,
             lcr: 0x03,
             mcr: 0,
@@ -52,13 +63,19 @@ impl Serial16550 {
     /// `OUT2` bit in the Modem Control Register (MCR). Software typically sets `OUT2` during UART
     /// initialization to enable interrupt delivery.
     pub fn irq_level(&self) -> bool {
-        self.interrupt_pending() && (self.mcr & 0x08) != 0
+        // In loopback mode OUT2 is routed back to MSR instead of gating the external line.
+        self.interrupt_id().is_some() && (self.mcr & 0x18) == 0x08
     }
 
     pub fn take_tx(&mut self) -> Vec<u8> {
         std::mem::take(&mut self.tx)
     }
 
+    /// Bytes transmitted since the last [`Self::take_tx`], without draining them.
+    pub fn pending_tx(&self) -> &[u8] {
+        &self.tx
+    }
+
     fn dlab(&self) -> bool {
         self.lcr & 0x80 != 0
     }
@@ -67,20 +84,60 @@ impl Serial16550 {
         (self.fcr & 0x01) != 0
     }
 
-    fn interrupt_pending(&self) -> bool {
-        // Receive Data Available.
-        (self.ier & 0x01) != 0 && !self.rx.is_empty()
+    fn loopback(&self) -> bool {
+        (self.mcr & 0x10) != 0
+    }
+
+    /// Receive FIFO trigger level selected by FCR bits 7:6.
+    fn rx_trigger_level(&self) -> usize {
+        match self.fcr >> 6 {
+            0 => 1,
+            1 => 4,
+            2 => 8,
+            _ => 14,
+        }
+    }
+
+    /// Highest-priority pending interrupt, as reported in IIR bits 3:0.
+    fn interrupt_id(&self) -> Option<u8> {
+        if (self.ier & 0x01) != 0 && !self.rx.is_empty() {
+            // With FIFOs enabled, a FIFO below the trigger level raises a character timeout
+            // instead. Input arrives in whole host writes, so the timeout is considered elapsed.
+            if self.fifo_enabled() && self.rx.len() < self.rx_trigger_level() {
+                return Some(0x0C);
+            }
+            return Some(0x04);
+        }
+        if (self.ier & 0x02) != 0 && self.thre_pending {
+            return Some(0x02);
+        }
+        None
     }
 
-    fn read_iir(&self) -> u8 {
+    fn read_iir(&mut self) -> u8 {
         // Bit 0: 1 = no interrupt pending.
         // Bits 3:1: interrupt ID.
         // Bits 7:6: FIFO enabled status (16550).
         let fifo_bits = if self.fifo_enabled() { 0xC0 } else { 0x00 };
-        if self.interrupt_pending() {
-            fifo_bits | 0x04
+        match self.interrupt_id() {
+            Some(id) => {
+                // Reading IIR acknowledges a THR-empty interrupt.
+                if id == 0x02 {
+                    self.thre_pending = false;
+                }
+                fifo_bits | id
+            }
+            None => fifo_bits | 0x01,
+        }
+    }
+
+    fn read_msr(&self) -> u8 {
+        if self.loopback() {
+            // DTR->DSR, RTS->CTS, OUT1->RI, OUT2->DCD.
+            let mcr = self.mcr;
+            ((mcr & 0x01) << 5) | ((mcr & 0x02) << 3) | ((mcr & 0x04) << 4) | ((mcr & 0x08) << 4)
         } else {
-            fifo_bits | 0x01
+            self.msr | 0xB0
         }
     }
 
@@ -118,7 +175,7 @@ impl Serial16550 {
                 }
                 lsr
             }
-            6 => self.msr,
+            6 => self.read_msr(),
             7 => self.scr,
             _ => 0xFF,
         }
@@ -134,13 +191,23 @@ impl Serial16550 {
                 if self.dlab() {
                     self.dll = value;
                 } else {
-                    self.tx.push(value);
+                    if self.loopback() {
+                        self.rx.push_back(value);
+                    } else {
+                        self.tx.push(value);
+                    }
+                    // The byte is "transmitted" immediately, so THR becomes empty again.
+                    self.thre_pending = true;
                 }
             }
             1 => {
                 if self.dlab() {
                     self.dlm = value;
                 } else {
+                    // Enabling the THR-empty interrupt while THR is empty raises it immediately.
+                    if (value & 0x02) != 0 && (self.ier & 0x02) == 0 {
+                        self.thre_pending = true;
+                    }
                     self.ier = value;
                 }
             }
@@ -221,6 +288,10 @@ impl PortIoDevice for Serial16550Port {
             _ => uart.write_u8(port, value as u8),
         }
     }
+
+    fn debug_name(&self) -> &'static str {
+        "serial16550"
+    }
 }
 
 pub fn register_serial16550(bus: &mut IoPortBus, uart: SharedSerial16550) {
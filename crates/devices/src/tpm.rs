@@ -0,0 +1,400 @@
+//! TPM 2.0 device exposing the Command Response Buffer (CRB) interface.
+//!
+//! This implements the register-level CRB protocol from the TCG PC Client Platform TPM Profile
+//! (PTP) specification for locality 0 only. Command processing itself is delegated to a
+//! [`TpmBackend`], so hosts can plug in a software TPM (or use [`NullTpmBackend`], which is just
+//! enough for guests that only probe for a TPM's presence).
+//!
+//! The guest-visible flow is:
+//! 1. request locality 0 via `TPM_LOC_CTRL_0.requestAccess`,
+//! 2. move the interface out of idle via `TPM_CRB_CTRL_REQ_0.cmdReady`,
+//! 3. write the command into the data buffer and set `TPM_CRB_CTRL_START_0`,
+//! 4. poll `TPM_CRB_CTRL_START_0` until it clears and read the response from the same buffer.
+//!
+//! Commands execute synchronously inside the `START` write, so `START` reads back as 0 as soon as
+//! the guest can observe it and cancellation is a no-op.
+
+use aero_io_snapshot::io::state::{
+    IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
+};
+
+pub const TPM_CRB_MMIO_BASE: u64 = 0xFED4_0000;
+/// Size of the CRB register window covering localities 0-4 (one 4KiB page each).
+pub const TPM_CRB_MMIO_SIZE: u64 = 0x5000;
+
+const LOCALITY_SIZE: u64 = 0x1000;
+
+const REG_LOC_STATE: u64 = 0x00;
+const REG_LOC_CTRL: u64 = 0x08;
+const REG_LOC_STS: u64 = 0x0C;
+const REG_INTF_ID_LO: u64 = 0x30;
+const REG_INTF_ID_HI: u64 = 0x34;
+const REG_CTRL_REQ: u64 = 0x40;
+const REG_CTRL_STS: u64 = 0x44;
+const REG_CTRL_CANCEL: u64 = 0x48;
+const REG_CTRL_START: u64 = 0x4C;
+const REG_INT_ENABLE: u64 = 0x50;
+const REG_INT_STS: u64 = 0x54;
+const REG_CMD_SIZE: u64 = 0x58;
+const REG_CMD_LADDR: u64 = 0x5C;
+const REG_CMD_HADDR: u64 = 0x60;
+const REG_RSP_SIZE: u64 = 0x64;
+const REG_RSP_ADDR_LO: u64 = 0x68;
+const REG_RSP_ADDR_HI: u64 = 0x6C;
+
+/// Offset of the shared command/response data buffer within locality 0.
+pub const TPM_CRB_DATA_BUFFER_OFFSET: u64 = 0x80;
+/// Size of the shared command/response data buffer.
+pub const TPM_CRB_DATA_BUFFER_SIZE: usize = (LOCALITY_SIZE - TPM_CRB_DATA_BUFFER_OFFSET) as usize;
+
+const LOC_STATE_TPM_ESTABLISHED: u32 = 1 << 0;
+const LOC_STATE_LOC_ASSIGNED: u32 = 1 << 1;
+const LOC_STATE_REG_VALID_STS: u32 = 1 << 7;
+
+const LOC_CTRL_REQUEST_ACCESS: u32 = 1 << 0;
+const LOC_CTRL_RELINQUISH: u32 = 1 << 1;
+
+const LOC_STS_GRANTED: u32 = 1 << 0;
+
+const CTRL_REQ_CMD_READY: u32 = 1 << 0;
+const CTRL_REQ_GO_IDLE: u32 = 1 << 1;
+
+const CTRL_STS_TPM_IDLE: u32 = 1 << 1;
+
+const CTRL_START_INVOKE: u32 = 1 << 0;
+
+// TPM_CRB_INTF_ID: CRB interface type/version, 64-byte transfers, CRB-only and locked.
+//
+// The vendor/device IDs match QEMU's `tpm-crb`, which guest drivers are routinely exercised
+// against.
+const INTF_ID_LO: u32 = 0x1 // InterfaceType: CRB
+    | (0x1 << 4) // InterfaceVersion: CRB
+    | (0x3 << 11) // CapDataXferSizeSupport: 64 bytes
+    | (1 << 14) // CapCRB
+    | (0x1 << 17) // InterfaceSelector: CRB
+    | (1 << 19); // IntfSelLock
+const INTF_ID_HI: u32 = 0x1014 | (0x0001 << 16);
+
+/// `TPM_ST_NO_SESSIONS`.
+const TPM_ST_NO_SESSIONS: u16 = 0x8001;
+/// Size of a TPM 2.0 command/response header (tag, size, code).
+const TPM_HEADER_SIZE: usize = 10;
+
+pub const TPM_RC_SUCCESS: u32 = 0x000;
+pub const TPM_RC_FAILURE: u32 = 0x101;
+pub const TPM_RC_COMMAND_SIZE: u32 = 0x142;
+
+pub const TPM2_CC_SELF_TEST: u32 = 0x143;
+pub const TPM2_CC_STARTUP: u32 = 0x144;
+pub const TPM2_CC_GET_RANDOM: u32 = 0x17B;
+
+/// Builds a header-only TPM 2.0 response carrying `rc`.
+pub fn tpm_response_code(rc: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(TPM_HEADER_SIZE);
+    out.extend_from_slice(&TPM_ST_NO_SESSIONS.to_be_bytes());
+    out.extend_from_slice(&(TPM_HEADER_SIZE as u32).to_be_bytes());
+    out.extend_from_slice(&rc.to_be_bytes());
+    out
+}
+
+/// Command processing behind a TPM device.
+///
+/// The CRB device owns the transport (locality, buffer and start/cancel handshake); a backend only
+/// sees complete command byte streams and returns complete responses.
+pub trait TpmBackend {
+    /// Executes one TPM 2.0 command and returns the response bytes.
+    ///
+    /// `command` always holds at least a full header and exactly the size that header declares.
+    /// Responses that do not fit the CRB data buffer are replaced by a `TPM_RC_FAILURE` response.
+    fn execute(&mut self, command: &[u8]) -> Vec<u8>;
+
+    /// Called on platform reset (`_TPM_Init`). Volatile state should be dropped here; the guest
+    /// is expected to issue `TPM2_Startup` again.
+    fn reset(&mut self) {}
+
+    /// Serializes backend state for VM snapshots.
+    ///
+    /// The default stores nothing, which is correct for stateless backends.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores state produced by [`TpmBackend::save_state`].
+    fn load_state(&mut self, _bytes: &[u8]) -> SnapshotResult<()> {
+        Ok(())
+    }
+}
+
+/// Backend that accepts `TPM2_Startup` and `TPM2_SelfTest` and fails everything else.
+///
+/// This is enough for guests and tooling that only check for a TPM's presence.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullTpmBackend;
+
+impl TpmBackend for NullTpmBackend {
+    fn execute(&mut self, command: &[u8]) -> Vec<u8> {
+        let code = u32::from_be_bytes(command[6..10].try_into().unwrap());
+        match code {
+            TPM2_CC_STARTUP | TPM2_CC_SELF_TEST => tpm_response_code(TPM_RC_SUCCESS),
+            _ => tpm_response_code(TPM_RC_FAILURE),
+        }
+    }
+}
+
+/// TPM 2.0 CRB interface (locality 0 only).
+pub struct TpmCrb {
+    backend: Box<dyn TpmBackend>,
+    loc_assigned: bool,
+    idle: bool,
+    cancel: u32,
+    int_enable: u32,
+    int_sts: u32,
+    buffer: Vec<u8>,
+}
+
+impl TpmCrb {
+    pub fn new(backend: Box<dyn TpmBackend>) -> Self {
+        Self {
+            backend,
+            loc_assigned: false,
+            idle: true,
+            cancel: 0,
+            int_enable: 0,
+            int_sts: 0,
+            buffer: vec![0; TPM_CRB_DATA_BUFFER_SIZE],
+        }
+    }
+
+    /// Replaces the command backend. Transport state (locality, buffer) is preserved.
+    pub fn set_backend(&mut self, backend: Box<dyn TpmBackend>) {
+        self.backend = backend;
+    }
+
+    pub fn backend(&self) -> &dyn TpmBackend {
+        &*self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut dyn TpmBackend {
+        &mut *self.backend
+    }
+
+    /// Platform reset: returns the interface to its power-on state and resets the backend.
+    pub fn reset(&mut self) {
+        self.loc_assigned = false;
+        self.idle = true;
+        self.cancel = 0;
+        self.int_enable = 0;
+        self.int_sts = 0;
+        self.buffer.fill(0);
+        self.backend.reset();
+    }
+
+    /// Whether locality 0 is currently granted.
+    pub fn locality_granted(&self) -> bool {
+        self.loc_assigned
+    }
+
+    pub fn mmio_read(&mut self, offset: u64, size: usize) -> u64 {
+        let size = size.clamp(1, 8);
+        let mut value = 0u64;
+        for i in 0..size {
+            let byte = self.read_u8(offset.wrapping_add(i as u64));
+            value |= u64::from(byte) << (i * 8);
+        }
+        value
+    }
+
+    pub fn mmio_write(&mut self, offset: u64, size: usize, value: u64) {
+        let size = size.clamp(1, 8);
+        if offset >= LOCALITY_SIZE {
+            // Only locality 0 is implemented; other localities are read-as-zero/write-ignored.
+            return;
+        }
+        if offset >= TPM_CRB_DATA_BUFFER_OFFSET {
+            for i in 0..size {
+                let off = offset + i as u64;
+                if off >= LOCALITY_SIZE {
+                    break;
+                }
+                self.buffer[(off - TPM_CRB_DATA_BUFFER_OFFSET) as usize] = (value >> (i * 8)) as u8;
+            }
+            return;
+        }
+
+        // Split the access into per-register (dword) writes so byte/word/qword accesses behave
+        // like their dword equivalents for the bits they cover.
+        let mut i = 0usize;
+        while i < size {
+            let off = offset + i as u64;
+            let reg = off & !3;
+            let shift = (off & 3) * 8;
+            let chunk = (4 - (off & 3) as usize).min(size - i);
+            let mask = if chunk == 4 {
+                u32::MAX
+            } else {
+                ((1u32 << (chunk * 8)) - 1) << shift
+            };
+            let bits = (((value >> (i * 8)) as u32) << shift) & mask;
+            self.write_reg(reg, bits, mask);
+            i += chunk;
+        }
+    }
+
+    fn read_u8(&self, offset: u64) -> u8 {
+        if offset >= LOCALITY_SIZE {
+            return 0;
+        }
+        if offset >= TPM_CRB_DATA_BUFFER_OFFSET {
+            return self.buffer[(offset - TPM_CRB_DATA_BUFFER_OFFSET) as usize];
+        }
+        let reg = self.read_reg(offset & !3);
+        (reg >> ((offset & 3) * 8)) as u8
+    }
+
+    fn read_reg(&self, reg: u64) -> u32 {
+        let buffer_addr = TPM_CRB_MMIO_BASE + TPM_CRB_DATA_BUFFER_OFFSET;
+        match reg {
+            REG_LOC_STATE => {
+                let mut v = LOC_STATE_TPM_ESTABLISHED | LOC_STATE_REG_VALID_STS;
+                if self.loc_assigned {
+                    // activeLocality (bits 2..4) is always 0.
+                    v |= LOC_STATE_LOC_ASSIGNED;
+                }
+                v
+            }
+            REG_LOC_STS => {
+                if self.loc_assigned {
+                    LOC_STS_GRANTED
+                } else {
+                    0
+                }
+            }
+            REG_INTF_ID_LO => INTF_ID_LO,
+            REG_INTF_ID_HI => INTF_ID_HI,
+            REG_CTRL_STS => {
+                if self.idle {
+                    CTRL_STS_TPM_IDLE
+                } else {
+                    0
+                }
+            }
+            REG_CTRL_CANCEL => self.cancel,
+            REG_INT_ENABLE => self.int_enable,
+            REG_INT_STS => self.int_sts,
+            REG_CMD_SIZE | REG_RSP_SIZE => TPM_CRB_DATA_BUFFER_SIZE as u32,
+            REG_CMD_LADDR | REG_RSP_ADDR_LO => buffer_addr as u32,
+            REG_CMD_HADDR | REG_RSP_ADDR_HI => (buffer_addr >> 32) as u32,
+            // LOC_CTRL is write-only, CTRL_REQ and CTRL_START complete synchronously, and
+            // CTRL_EXT is unused.
+            _ => 0,
+        }
+    }
+
+    fn write_reg(&mut self, reg: u64, bits: u32, mask: u32) {
+        match reg {
+            REG_LOC_CTRL => {
+                if bits & LOC_CTRL_REQUEST_ACCESS != 0 {
+                    self.loc_assigned = true;
+                }
+                if bits & LOC_CTRL_RELINQUISH != 0 {
+                    self.loc_assigned = false;
+                }
+            }
+            REG_CTRL_REQ => {
+                if bits & CTRL_REQ_CMD_READY != 0 {
+                    self.idle = false;
+                }
+                if bits & CTRL_REQ_GO_IDLE != 0 {
+                    self.idle = true;
+                }
+            }
+            REG_CTRL_CANCEL => self.cancel = (self.cancel & !mask) | bits,
+            REG_CTRL_START => {
+                if bits & CTRL_START_INVOKE != 0 && self.loc_assigned {
+                    self.execute_command();
+                }
+            }
+            REG_INT_ENABLE => self.int_enable = (self.int_enable & !mask) | bits,
+            // Write-1-to-clear.
+            REG_INT_STS => self.int_sts &= !bits,
+            _ => {}
+        }
+    }
+
+    fn execute_command(&mut self) {
+        let declared = u32::from_be_bytes(self.buffer[2..6].try_into().unwrap()) as usize;
+        let response = if (TPM_HEADER_SIZE..=self.buffer.len()).contains(&declared) {
+            let response = self.backend.execute(&self.buffer[..declared]);
+            if (TPM_HEADER_SIZE..=self.buffer.len()).contains(&response.len()) {
+                response
+            } else {
+                tpm_response_code(TPM_RC_FAILURE)
+            }
+        } else {
+            tpm_response_code(TPM_RC_COMMAND_SIZE)
+        };
+        self.buffer[..response.len()].copy_from_slice(&response);
+        self.cancel = 0;
+    }
+}
+
+impl IoSnapshot for TpmCrb {
+    const DEVICE_ID: [u8; 4] = *b"TPMC";
+    const DEVICE_VERSION: SnapshotVersion = SnapshotVersion::new(1, 0);
+
+    fn save_state(&self) -> Vec<u8> {
+        const TAG_LOC_ASSIGNED: u16 = 1;
+        const TAG_IDLE: u16 = 2;
+        const TAG_CANCEL: u16 = 3;
+        const TAG_INT_ENABLE: u16 = 4;
+        const TAG_INT_STS: u16 = 5;
+        const TAG_BUFFER: u16 = 6;
+        const TAG_BACKEND: u16 = 7;
+
+        let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
+        w.field_bool(TAG_LOC_ASSIGNED, self.loc_assigned);
+        w.field_bool(TAG_IDLE, self.idle);
+        w.field_u32(TAG_CANCEL, self.cancel);
+        w.field_u32(TAG_INT_ENABLE, self.int_enable);
+        w.field_u32(TAG_INT_STS, self.int_sts);
+        w.field_bytes(TAG_BUFFER, self.buffer.clone());
+        w.field_bytes(TAG_BACKEND, self.backend.save_state());
+        w.finish()
+    }
+
+    fn load_state(&mut self, bytes: &[u8]) -> SnapshotResult<()> {
+        const TAG_LOC_ASSIGNED: u16 = 1;
+        const TAG_IDLE: u16 = 2;
+        const TAG_CANCEL: u16 = 3;
+        const TAG_INT_ENABLE: u16 = 4;
+        const TAG_INT_STS: u16 = 5;
+        const TAG_BUFFER: u16 = 6;
+        const TAG_BACKEND: u16 = 7;
+
+        let r = SnapshotReader::parse(bytes, Self::DEVICE_ID)?;
+        r.ensure_device_major(Self::DEVICE_VERSION.major)?;
+
+        let loc_assigned = r.bool(TAG_LOC_ASSIGNED)?.unwrap_or(false);
+        let idle = r.bool(TAG_IDLE)?.unwrap_or(true);
+        let cancel = r.u32(TAG_CANCEL)?.unwrap_or(0);
+        let int_enable = r.u32(TAG_INT_ENABLE)?.unwrap_or(0);
+        let int_sts = r.u32(TAG_INT_STS)?.unwrap_or(0);
+        let mut buffer = vec![0u8; TPM_CRB_DATA_BUFFER_SIZE];
+        if let Some(saved) = r.bytes(TAG_BUFFER) {
+            if saved.len() != buffer.len() {
+                return Err(SnapshotError::InvalidFieldEncoding("tpm crb buffer length"));
+            }
+            buffer.copy_from_slice(saved);
+        }
+
+        self.backend
+            .load_state(r.bytes(TAG_BACKEND).unwrap_or(&[]))?;
+        self.loc_assigned = loc_assigned;
+        self.idle = idle;
+        self.cancel = cancel;
+        self.int_enable = int_enable;
+        self.int_sts = int_sts;
+        self.buffer = buffer;
+        Ok(())
+    }
+}
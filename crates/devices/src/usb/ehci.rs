@@ -11,9 +11,10 @@ use aero_io_snapshot::io::state::codec::{Decoder, Encoder};
 use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
-use aero_platform::memory::MemoryBus;
+use aero_platform::memory::{BusMasterMemory, MemoryBus};
 use aero_usb::ehci::EhciController;
 pub use aero_usb::ehci::{regs, regs::*};
+use memory::MemoryBus as _;
 use memory::MmioHandler;
 
 /// PCI wrapper for an emulated EHCI controller.
@@ -77,7 +78,7 @@ impl EhciPciDevice {
     /// Advance the controller by 1ms using the platform's canonical physical memory bus.
     pub fn tick_1ms(&mut self, mem: &mut MemoryBus) {
         enum AeroUsbMemoryBus<'a> {
-            Dma(&'a mut MemoryBus),
+            Dma(BusMasterMemory<'a>),
             NoDma,
         }
 
@@ -106,7 +107,7 @@ impl EhciPciDevice {
         // access guest memory for schedule structures.
         let dma_enabled = (self.config.command() & (1 << 2)) != 0;
         let mut adapter = if dma_enabled {
-            AeroUsbMemoryBus::Dma(mem)
+            AeroUsbMemoryBus::Dma(mem.bus_master())
         } else {
             AeroUsbMemoryBus::NoDma
         };
@@ -10,9 +10,10 @@ use aero_io_snapshot::io::state::{
     IoSnapshot, SnapshotError, SnapshotReader, SnapshotResult, SnapshotVersion, SnapshotWriter,
 };
 use aero_platform::io::{IoPortBus, PortIoDevice};
-use aero_platform::memory::MemoryBus;
+use aero_platform::memory::{BusMasterMemory, MemoryBus};
 use aero_usb::uhci::UhciController;
 pub use aero_usb::uhci::{regs, regs::*};
+use memory::MemoryBus as _;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -59,7 +60,7 @@ impl UhciPciDevice {
     /// Advance the controller by 1ms using the platform's canonical physical memory bus.
     pub fn tick_1ms(&mut self, mem: &mut MemoryBus) {
         enum AeroUsbMemoryBus<'a> {
-            Dma(&'a mut MemoryBus),
+            Dma(BusMasterMemory<'a>),
             NoDma,
         }
 
@@ -88,7 +89,7 @@ impl UhciPciDevice {
         // access guest memory for the schedule/frame list.
         let dma_enabled = (self.config.command() & (1 << 2)) != 0;
         let mut adapter = if dma_enabled {
-            AeroUsbMemoryBus::Dma(mem)
+            AeroUsbMemoryBus::Dma(mem.bus_master())
         } else {
             AeroUsbMemoryBus::NoDma
         };
@@ -284,6 +285,10 @@ impl PortIoDevice for UhciPciPort {
         debug_assert_eq!(port, self.port);
         self.dev.borrow_mut().port_write(port, size, value);
     }
+
+    fn debug_name(&self) -> &'static str {
+        "uhci"
+    }
 }
 
 /// Convenience helper to register UHCI's I/O BAR ports on an [`IoPortBus`].
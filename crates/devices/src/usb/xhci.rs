@@ -282,7 +282,7 @@ impl XhciPciDevice {
 
         // This helper is test-only; it always uses the provided platform memory bus, regardless of
         // PCI Bus Master Enable gating.
-        let mut adapter = AeroUsbMemoryBus::Dma(mem);
+        let mut adapter = AeroUsbMemoryBus::Dma(&mut mem.bus_master());
         self.controller.service_event_ring(&mut adapter);
         self.service_interrupts();
     }
@@ -301,11 +301,11 @@ impl XhciPciDevice {
                     let mut adapter = AeroUsbMemoryBus::Dma(&mut *mem_ref);
                     self.controller.tick_1ms(&mut adapter);
                 } else {
-                    let mut adapter = AeroUsbMemoryBus::Dma(mem as &mut dyn memory::MemoryBus);
+                    let mut adapter = AeroUsbMemoryBus::Dma(&mut mem.bus_master());
                     self.controller.tick_1ms(&mut adapter);
                 }
             } else {
-                let mut adapter = AeroUsbMemoryBus::Dma(mem as &mut dyn memory::MemoryBus);
+                let mut adapter = AeroUsbMemoryBus::Dma(&mut mem.bus_master());
                 self.controller.tick_1ms(&mut adapter);
             }
         } else {
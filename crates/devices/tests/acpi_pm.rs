@@ -1,3 +1,4 @@
+use aero_acpi::AERO_GPE_LID;
 use aero_devices::acpi_pm::{
     register_acpi_pm, AcpiPmCallbacks, AcpiPmConfig, AcpiPmIo, AcpiSleepState, PM1_STS_PWRBTN,
     PM1_STS_SLPBTN, PM1_STS_WAK, SLP_TYP_S1, SLP_TYP_S3, SLP_TYP_S4, SLP_TYP_S5,
@@ -411,3 +412,59 @@ fn wak_sts_does_not_assert_sci_by_itself() {
     );
     assert!(!irq.level());
 }
+
+#[test]
+fn gpe_assert_sci_and_write_one_to_clear_round_trip() {
+    let cfg = AcpiPmConfig::default();
+    let sts = cfg.gpe0_blk;
+    let en = cfg.gpe0_blk + u16::from(cfg.gpe0_blk_len) / 2;
+
+    let sci_log: Rc<RefCell<Vec<bool>>> = Rc::new(RefCell::new(Vec::new()));
+    let callbacks = AcpiPmCallbacks {
+        sci_irq: Box::new(TestIrqLine(sci_log.clone())),
+        ..Default::default()
+    };
+    let pm = Rc::new(RefCell::new(AcpiPmIo::new_with_callbacks(cfg, callbacks)));
+    let mut bus = IoPortBus::new();
+    register_acpi_pm(&mut bus, pm.clone());
+    assert_eq!(pm.borrow().gpe0_count(), 32);
+    bus.write(cfg.smi_cmd_port, 1, u32::from(cfg.acpi_enable_cmd));
+
+    // A disabled GPE latches status without raising SCI.
+    pm.borrow_mut().assert_gpe(AERO_GPE_LID);
+    assert_eq!(bus.read(sts, 1), 1 << AERO_GPE_LID);
+    assert!(!pm.borrow().sci_level());
+
+    // Enabling it raises SCI; so does a second, already-enabled GPE in another status byte.
+    bus.write(en, 4, (1 << AERO_GPE_LID) | (1 << 9));
+    assert!(pm.borrow().sci_level());
+    pm.borrow_mut().assert_gpe(9);
+    assert_eq!(bus.read(sts, 4), (1 << AERO_GPE_LID) | (1 << 9));
+
+    // Writing zeros, or ones to clear bits, leaves pending status alone.
+    bus.write(sts, 4, 0);
+    bus.write(sts, 1, !(1u32 << AERO_GPE_LID) & 0xFF);
+    assert_eq!(bus.read(sts, 4), (1 << AERO_GPE_LID) | (1 << 9));
+
+    // Clearing one GPE keeps SCI asserted for the other.
+    bus.write(sts, 1, 1 << AERO_GPE_LID);
+    assert_eq!(bus.read(sts, 4), 1 << 9);
+    assert!(pm.borrow().sci_level());
+
+    // Masking the remaining one drops SCI while its status stays latched; the guest-style clear
+    // then leaves nothing to unmask.
+    bus.write(en + 1, 1, 0);
+    assert!(!pm.borrow().sci_level());
+    assert_eq!(bus.read(sts + 1, 1), 1 << 1);
+    bus.write(sts + 1, 1, 1 << 1);
+    bus.write(en + 1, 1, 1 << 1);
+    assert!(!pm.borrow().sci_level());
+    assert_eq!(sci_log.borrow().as_slice(), &[true, false]);
+
+    // The GPE enable register is plain read/write.
+    assert_eq!(bus.read(en, 4), (1 << AERO_GPE_LID) | (1 << 9));
+
+    // Events outside the block are ignored.
+    pm.borrow_mut().assert_gpe(32);
+    assert_eq!(bus.read(sts, 4), 0);
+}
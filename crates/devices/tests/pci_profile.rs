@@ -160,6 +160,21 @@ fn aerogpu_profile_matches_protocol_constants() {
         AEROGPU_VRAM_SIZE,
         u64::from(protocol_pci::AEROGPU_PCI_BAR1_SIZE_BYTES)
     );
+
+    assert_eq!(
+        u32::from(AEROGPU_MSIX_TABLE_SIZE),
+        protocol_pci::AEROGPU_MSIX_VECTOR_COUNT
+    );
+    assert_eq!(
+        AEROGPU_MSIX_TABLE_OFFSET,
+        protocol_pci::AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES
+    );
+    assert_eq!(
+        AEROGPU_MSIX_PBA_OFFSET,
+        protocol_pci::AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES
+    );
+    assert_eq!(AEROGPU_MSIX_TABLE_BAR, AEROGPU_BAR0_INDEX);
+    assert_eq!(AEROGPU_MSIX_PBA_BAR, AEROGPU_BAR0_INDEX);
 }
 
 #[test]
@@ -502,6 +517,25 @@ fn xhci_config_space_exposes_msi_and_msix_capabilities() {
     assert_eq!(pba, 0x9000);
 }
 
+#[test]
+fn aerogpu_config_space_exposes_msix_capability() {
+    let mut cfg = AEROGPU.build_config_space();
+    let caps = cfg.capability_list();
+    assert_eq!(caps.len(), 1);
+    assert_eq!(caps[0].id, PCI_CAP_ID_MSIX);
+
+    let msix_off = cfg.find_capability(PCI_CAP_ID_MSIX).unwrap() as u16;
+    let msix_ctrl = cfg.read(msix_off + 0x02, 2) as u16;
+    // Table size is encoded as N-1; AeroGPU exposes fence, vblank and error vectors.
+    assert_eq!(msix_ctrl & 0x07ff, 2);
+    assert_eq!(msix_ctrl & (1 << 15), 0, "MSI-X should start disabled");
+
+    let table = cfg.read(msix_off + 0x04, 4);
+    assert_eq!(table, 0x8000);
+    let pba = cfg.read(msix_off + 0x08, 4);
+    assert_eq!(pba, 0x9000);
+}
+
 #[test]
 fn virtio_bar0_is_64bit_mmio() {
     let mut cfg = VIRTIO_NET.build_config_space();
@@ -48,3 +48,55 @@ fn fifo_enable_sets_iir_fifo_status_bits_and_clear_rx_works() {
     uart.write_u8(COM1 + 2, 0x03); // FIFO enable + clear RX
     assert_eq!(uart.read_u8(COM1), 0x00);
 }
+
+#[test]
+fn fifo_trigger_level_selects_timeout_or_data_available() {
+    let mut uart = Serial16550::new(COM1);
+    uart.write_u8(COM1 + 1, 0x01);
+    // FIFO enable with a 4-byte receive trigger level.
+    uart.write_u8(COM1 + 2, 0x41);
+
+    uart.push_rx(1);
+    uart.push_rx(2);
+    assert_eq!(uart.read_u8(COM1 + 2), 0xCC);
+
+    uart.push_rx(3);
+    uart.push_rx(4);
+    assert_eq!(uart.read_u8(COM1 + 2), 0xC4);
+}
+
+#[test]
+fn thr_empty_interrupt_is_acknowledged_by_iir_read_and_rearmed_by_thr_write() {
+    let mut uart = Serial16550::new(COM1);
+    uart.write_u8(COM1 + 4, 0x08);
+    uart.write_u8(COM1 + 1, 0x02);
+    assert!(uart.irq_level());
+    assert_eq!(uart.read_u8(COM1 + 2), 0x02);
+    assert_eq!(uart.read_u8(COM1 + 2), 0x01);
+    assert!(!uart.irq_level());
+
+    uart.write_u8(COM1, b'x');
+    assert!(uart.irq_level());
+
+    // Received data outranks THR empty.
+    uart.write_u8(COM1 + 1, 0x03);
+    uart.push_rx(0x55);
+    assert_eq!(uart.read_u8(COM1 + 2), 0x04);
+}
+
+#[test]
+fn loopback_routes_tx_to_rx_and_mirrors_mcr_in_msr() {
+    let mut uart = Serial16550::new(COM1);
+    // Outside loopback the peer looks connected (CTS, DSR, DCD).
+    assert_eq!(uart.read_u8(COM1 + 6), 0xB0);
+
+    uart.write_u8(COM1 + 4, 0x10 | 0x0A);
+    assert_eq!(uart.read_u8(COM1 + 6), 0x90);
+    uart.write_u8(COM1 + 4, 0x10 | 0x05);
+    assert_eq!(uart.read_u8(COM1 + 6), 0x60);
+
+    uart.write_u8(COM1, 0xA5);
+    assert!(uart.take_tx().is_empty());
+    assert_eq!(uart.read_u8(COM1 + 5) & 0x01, 0x01);
+    assert_eq!(uart.read_u8(COM1), 0xA5);
+}
@@ -0,0 +1,101 @@
+use aero_devices::tpm::{
+    tpm_response_code, NullTpmBackend, TpmBackend, TpmCrb, TPM_CRB_DATA_BUFFER_OFFSET,
+    TPM_CRB_DATA_BUFFER_SIZE, TPM_RC_COMMAND_SIZE, TPM_RC_FAILURE, TPM_RC_SUCCESS,
+};
+use aero_io_snapshot::io::state::IoSnapshot;
+
+const LOC_CTRL: u64 = 0x08;
+const LOC_STS: u64 = 0x0C;
+const CTRL_START: u64 = 0x4C;
+
+struct Oversized;
+
+impl TpmBackend for Oversized {
+    fn execute(&mut self, _command: &[u8]) -> Vec<u8> {
+        vec![0; TPM_CRB_DATA_BUFFER_SIZE + 1]
+    }
+}
+
+fn write_buffer(tpm: &mut TpmCrb, bytes: &[u8]) {
+    for (i, b) in bytes.iter().enumerate() {
+        tpm.mmio_write(TPM_CRB_DATA_BUFFER_OFFSET + i as u64, 1, u64::from(*b));
+    }
+}
+
+fn read_buffer(tpm: &mut TpmCrb, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| tpm.mmio_read(TPM_CRB_DATA_BUFFER_OFFSET + i as u64, 1) as u8)
+        .collect()
+}
+
+fn run(tpm: &mut TpmCrb, cmd: &[u8]) -> Vec<u8> {
+    write_buffer(tpm, cmd);
+    tpm.mmio_write(CTRL_START, 4, 1);
+    read_buffer(tpm, 10)
+}
+
+fn startup_with_size(size: u32) -> Vec<u8> {
+    let mut cmd = vec![0x80, 0x01];
+    cmd.extend_from_slice(&size.to_be_bytes());
+    cmd.extend_from_slice(&0x144u32.to_be_bytes());
+    cmd.extend_from_slice(&[0, 0]);
+    cmd
+}
+
+#[test]
+fn byte_access_to_loc_ctrl_and_status_registers() {
+    let mut tpm = TpmCrb::new(Box::new(NullTpmBackend));
+    tpm.mmio_write(LOC_CTRL, 1, 1);
+    assert!(tpm.locality_granted());
+    assert_eq!(tpm.mmio_read(LOC_STS, 1), 1);
+    // A qword read spanning LOC_CTRL (write-only) and LOC_STS.
+    assert_eq!(tpm.mmio_read(LOC_CTRL, 8), 1 << 32);
+    // Other localities are not implemented.
+    tpm.mmio_write(0x1008, 4, 1);
+    assert_eq!(tpm.mmio_read(0x100C, 4), 0);
+}
+
+#[test]
+fn malformed_commands_and_oversized_responses_are_rejected() {
+    let mut tpm = TpmCrb::new(Box::new(NullTpmBackend));
+    tpm.mmio_write(LOC_CTRL, 4, 1);
+
+    assert_eq!(
+        run(&mut tpm, &startup_with_size(12)),
+        tpm_response_code(TPM_RC_SUCCESS)
+    );
+    assert_eq!(
+        run(&mut tpm, &startup_with_size(4)),
+        tpm_response_code(TPM_RC_COMMAND_SIZE)
+    );
+    assert_eq!(
+        run(
+            &mut tpm,
+            &startup_with_size(TPM_CRB_DATA_BUFFER_SIZE as u32 + 1)
+        ),
+        tpm_response_code(TPM_RC_COMMAND_SIZE)
+    );
+
+    tpm.set_backend(Box::new(Oversized));
+    assert_eq!(
+        run(&mut tpm, &startup_with_size(12)),
+        tpm_response_code(TPM_RC_FAILURE)
+    );
+}
+
+#[test]
+fn snapshot_roundtrip_preserves_buffer_and_locality() {
+    let mut tpm = TpmCrb::new(Box::new(NullTpmBackend));
+    tpm.mmio_write(LOC_CTRL, 4, 1);
+    write_buffer(&mut tpm, &[0xAB; 16]);
+    let state = tpm.save_state();
+
+    let mut restored = TpmCrb::new(Box::new(NullTpmBackend));
+    restored.load_state(&state).unwrap();
+    assert!(restored.locality_granted());
+    assert_eq!(read_buffer(&mut restored, 16), vec![0xAB; 16]);
+
+    restored.reset();
+    assert!(!restored.locality_granted());
+    assert_eq!(read_buffer(&mut restored, 16), vec![0; 16]);
+}
@@ -11,6 +11,8 @@ fn disabled_scanout_state_update(source: u32) -> ScanoutStateUpdate {
         pitch_bytes: 0,
         // Keep format at a stable default even while disabled.
         format: SCANOUT_FORMAT_B8G8R8X8,
+        slot0_offset: 0,
+        slot1_offset: 0,
     }
 }
 
@@ -81,6 +83,8 @@ pub fn scanout_config_to_scanout_state_update(
         height,
         pitch_bytes: cfg.pitch_bytes,
         format,
+        slot0_offset: 0,
+        slot1_offset: 0,
     }
 }
 
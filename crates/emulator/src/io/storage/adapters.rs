@@ -94,12 +94,20 @@ pub fn aero_storage_disk_error_to_emulator(err: aero_storage::DiskError) -> Disk
         aero_storage::DiskError::InvalidSparseHeader(msg) => DiskError::CorruptImage(msg),
         aero_storage::DiskError::InvalidConfig(msg) => DiskError::Unsupported(msg),
         aero_storage::DiskError::CorruptSparseImage(msg) => DiskError::CorruptImage(msg),
+        aero_storage::DiskError::ChecksumMismatch { .. } => {
+            DiskError::CorruptImage("sparse block checksum mismatch")
+        }
         aero_storage::DiskError::NotSupported(msg) => DiskError::NotSupported(msg),
         aero_storage::DiskError::QuotaExceeded => DiskError::QuotaExceeded,
         aero_storage::DiskError::InUse => DiskError::InUse,
         aero_storage::DiskError::InvalidState(msg) => DiskError::InvalidState(msg),
         aero_storage::DiskError::BackendUnavailable => DiskError::BackendUnavailable,
         aero_storage::DiskError::Io(msg) => DiskError::Io(msg),
+        err @ aero_storage::DiskError::NativeIo { .. } => DiskError::Io(err.to_string()),
+        // The emulator error type has no layer context; map the underlying cause.
+        aero_storage::DiskError::Context { source, .. } => {
+            aero_storage_disk_error_to_emulator(*source)
+        }
     }
 }
 
@@ -111,7 +119,7 @@ pub fn aero_storage_disk_error_to_emulator_with_sector_context(
     sectors: u64,
     capacity_sectors: u64,
 ) -> DiskError {
-    match err {
+    match err.root() {
         aero_storage::DiskError::OutOfBounds { .. } | aero_storage::DiskError::OffsetOverflow => {
             DiskError::OutOfRange {
                 lba,
@@ -119,7 +127,7 @@ pub fn aero_storage_disk_error_to_emulator_with_sector_context(
                 capacity_sectors,
             }
         }
-        other => aero_storage_disk_error_to_emulator(other),
+        _ => aero_storage_disk_error_to_emulator(err),
     }
 }
 
@@ -35,6 +35,7 @@ impl<S: aero_storage::StorageBackend> AerosparDisk<S> {
             AeroSparseConfig {
                 disk_size_bytes,
                 block_size_bytes: block_size,
+                ..Default::default()
             },
         )
         .map_err(aero_storage_disk_error_to_emulator)?;
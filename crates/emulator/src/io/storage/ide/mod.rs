@@ -69,11 +69,14 @@ struct IsoBackendAdapter {
 }
 
 impl aero_devices_storage::atapi::IsoBackend for IsoBackendAdapter {
-    fn sector_count(&self) -> u32 {
-        self.inner.sector_count()
+    fn sector_count(&self) -> u64 {
+        u64::from(self.inner.sector_count())
     }
 
-    fn read_sectors(&mut self, lba: u32, buf: &mut [u8]) -> io::Result<()> {
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> io::Result<()> {
+        // Legacy backends cannot address beyond their 32-bit sector count anyway.
+        let lba = u32::try_from(lba)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "LBA out of range"))?;
         self.inner.read_sectors(lba, buf).map_err(io::Error::other)
     }
 }
@@ -75,6 +75,7 @@ fn emulator_opens_aero_storage_aerospar() {
         AeroSparseConfig {
             disk_size_bytes: 16 * 1024 * 1024,
             block_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
     )
     .unwrap();
@@ -1,8 +1,9 @@
 use aero_storage::{MemBackend, RawDisk, VirtualDisk as _};
 
 use emulator::io::storage::adapters::{
-    aero_storage_disk_error_to_emulator, emulator_disk_error_to_aero_storage,
-    EmuDiskBackendFromVirtualDisk, VirtualDiskFromEmuDiskBackend,
+    aero_storage_disk_error_to_emulator, aero_storage_disk_error_to_emulator_with_sector_context,
+    emulator_disk_error_to_aero_storage, EmuDiskBackendFromVirtualDisk,
+    VirtualDiskFromEmuDiskBackend,
 };
 use emulator::io::storage::disk::MemDisk as EmuMemDisk;
 use emulator::io::storage::{DiskBackend as _, DiskError};
@@ -50,6 +51,31 @@ fn error_mapping_preserves_offset_overflow_roundtrip() {
     assert!(matches!(back, aero_storage::DiskError::OffsetOverflow));
 }
 
+#[test]
+fn sector_context_mapping_sees_through_layer_context() {
+    let err = aero_storage::DiskError::OutOfBounds {
+        offset: 4096,
+        len: 512,
+        capacity: 4096,
+    }
+    .with_context(aero_storage::DiskLayer::Vhd, 4096, 512);
+    let emu = aero_storage_disk_error_to_emulator_with_sector_context(err, 8, 1, 8);
+    assert_eq!(
+        emu,
+        DiskError::OutOfRange {
+            lba: 8,
+            sectors: 1,
+            capacity_sectors: 8,
+        }
+    );
+
+    let err = aero_storage::DiskError::OffsetOverflow
+        .in_layer(aero_storage::DiskLayer::BlockCache)
+        .with_context(aero_storage::DiskLayer::Vhd, 0, 512);
+    let emu = aero_storage_disk_error_to_emulator_with_sector_context(err, u64::MAX, 1, 8);
+    assert!(matches!(emu, DiskError::OutOfRange { .. }));
+}
+
 #[test]
 fn error_mapping_preserves_corrupt_image_roundtrip() {
     let err = aero_storage::DiskError::CorruptImage("bad");
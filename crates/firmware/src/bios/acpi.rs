@@ -1,6 +1,11 @@
-use aero_acpi::{AcpiConfig, AcpiPlacement, AcpiTables, PhysicalMemory as AcpiPhysicalMemory};
+use aero_acpi::{
+    AcpiConfig, AcpiNumaNode, AcpiPlacement, AcpiTables, PhysicalMemory as AcpiPhysicalMemory,
+};
 
-use super::{BiosBus, PCIE_ECAM_BASE, PCIE_ECAM_END_BUS, PCIE_ECAM_SEGMENT, PCIE_ECAM_START_BUS};
+use super::{
+    ram_physical_ranges, BiosBus, PCIE_ECAM_BASE, PCIE_ECAM_END_BUS, PCIE_ECAM_SEGMENT,
+    PCIE_ECAM_START_BUS,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct AcpiInfo {
@@ -11,6 +16,19 @@ pub struct AcpiInfo {
     pub nvs: (u64, u64),
 }
 
+/// One NUMA node published via the ACPI `SRAT`/`SLIT` tables.
+///
+/// Nodes own consecutive slices of guest RAM in configuration order: the first node gets the first
+/// `mem_bytes` of RAM, and so on. Slices that reach past the low RAM window are reported at their
+/// remapped address above 4GiB (see [`ram_physical_ranges`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumaNodeConfig {
+    /// vCPU indices (equal to their APIC IDs) belonging to this node.
+    pub cpus: Vec<u8>,
+    /// Bytes of guest RAM belonging to this node.
+    pub mem_bytes: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BiosAcpiError {
     TableAddressOverflow {
@@ -68,12 +86,16 @@ impl core::fmt::Display for BiosAcpiError {
 impl std::error::Error for BiosAcpiError {}
 
 pub trait AcpiBuilder: Send {
+    #[allow(clippy::too_many_arguments)]
     fn build_and_write(
         &mut self,
         bus: &mut dyn BiosBus,
         memory_size_bytes: u64,
         cpu_count: u8,
         pirq_to_gsi: [u32; 4],
+        tpm_crb_base: Option<u64>,
+        second_io_apic_base: Option<u32>,
+        numa_nodes: &[NumaNodeConfig],
         placement: AcpiPlacement,
     ) -> Result<AcpiInfo, BiosAcpiError>;
 }
@@ -88,17 +110,33 @@ impl AcpiBuilder for AeroAcpiBuilder {
         memory_size_bytes: u64,
         cpu_count: u8,
         pirq_to_gsi: [u32; 4],
+        tpm_crb_base: Option<u64>,
+        second_io_apic_base: Option<u32>,
+        numa_nodes: &[NumaNodeConfig],
         placement: AcpiPlacement,
     ) -> Result<AcpiInfo, BiosAcpiError> {
-        build_and_write(bus, memory_size_bytes, cpu_count, pirq_to_gsi, placement)
+        build_and_write(
+            bus,
+            memory_size_bytes,
+            cpu_count,
+            pirq_to_gsi,
+            tpm_crb_base,
+            second_io_apic_base,
+            numa_nodes,
+            placement,
+        )
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_and_write(
     bus: &mut dyn BiosBus,
     memory_size_bytes: u64,
     cpu_count: u8,
     pirq_to_gsi: [u32; 4],
+    tpm_crb_base: Option<u64>,
+    second_io_apic_base: Option<u32>,
+    numa_nodes: &[NumaNodeConfig],
     placement: AcpiPlacement,
 ) -> Result<AcpiInfo, BiosAcpiError> {
     let cfg = AcpiConfig {
@@ -111,6 +149,9 @@ pub fn build_and_write(
         pcie_segment: PCIE_ECAM_SEGMENT,
         pcie_start_bus: PCIE_ECAM_START_BUS,
         pcie_end_bus: PCIE_ECAM_END_BUS,
+        tpm_crb_base: tpm_crb_base.unwrap_or(0),
+        second_io_apic_addr: second_io_apic_base.unwrap_or(0),
+        numa_nodes: acpi_numa_nodes(numa_nodes),
         ..Default::default()
     };
 
@@ -130,6 +171,15 @@ pub fn build_and_write(
     if let (Some(addr), Some(table)) = (tables.addresses.mcfg, tables.mcfg.as_ref()) {
         to_check.push(("MCFG", addr, table.len()));
     }
+    if let (Some(addr), Some(table)) = (tables.addresses.tpm2, tables.tpm2.as_ref()) {
+        to_check.push(("TPM2", addr, table.len()));
+    }
+    if let (Some(addr), Some(table)) = (tables.addresses.srat, tables.srat.as_ref()) {
+        to_check.push(("SRAT", addr, table.len()));
+    }
+    if let (Some(addr), Some(table)) = (tables.addresses.slit, tables.slit.as_ref()) {
+        to_check.push(("SLIT", addr, table.len()));
+    }
     for (name, addr, len) in to_check {
         let Some(end) = addr.checked_add(len as u64) else {
             return Err(BiosAcpiError::TableAddressOverflow {
@@ -179,6 +229,21 @@ pub fn build_and_write(
     })
 }
 
+fn acpi_numa_nodes(nodes: &[NumaNodeConfig]) -> Vec<AcpiNumaNode> {
+    let mut ram_offset = 0u64;
+    nodes
+        .iter()
+        .map(|node| {
+            let memory = ram_physical_ranges(ram_offset, node.mem_bytes);
+            ram_offset = ram_offset.saturating_add(node.mem_bytes);
+            AcpiNumaNode {
+                apic_ids: node.cpus.clone(),
+                memory,
+            }
+        })
+        .collect()
+}
+
 fn acpi_reclaimable_region_from_tables(tables: &AcpiTables) -> (u64, u64) {
     let addrs = &tables.addresses;
     let mut start = addrs.dsdt;
@@ -188,6 +253,12 @@ fn acpi_reclaimable_region_from_tables(tables: &AcpiTables) -> (u64, u64) {
     if let Some(mcfg) = addrs.mcfg {
         start = start.min(mcfg);
     }
+    if let Some(tpm2) = addrs.tpm2 {
+        start = start.min(tpm2);
+    }
+    for addr in [addrs.srat, addrs.slit].into_iter().flatten() {
+        start = start.min(addr);
+    }
     start = start.min(addrs.rsdt);
     start = start.min(addrs.xsdt);
 
@@ -199,6 +270,15 @@ fn acpi_reclaimable_region_from_tables(tables: &AcpiTables) -> (u64, u64) {
     if let (Some(addr), Some(table)) = (addrs.mcfg, tables.mcfg.as_ref()) {
         end = end.max(addr.saturating_add(table.len() as u64));
     }
+    if let (Some(addr), Some(table)) = (addrs.tpm2, tables.tpm2.as_ref()) {
+        end = end.max(addr.saturating_add(table.len() as u64));
+    }
+    if let (Some(addr), Some(table)) = (addrs.srat, tables.srat.as_ref()) {
+        end = end.max(addr.saturating_add(table.len() as u64));
+    }
+    if let (Some(addr), Some(table)) = (addrs.slit, tables.slit.as_ref()) {
+        end = end.max(addr.saturating_add(table.len() as u64));
+    }
     end = end.max(addrs.rsdt.saturating_add(tables.rsdt.len() as u64));
     end = end.max(addrs.xsdt.saturating_add(tables.xsdt.len() as u64));
 
@@ -0,0 +1,349 @@
+//! Boot-order selection and the interactive boot menu.
+//!
+//! When [`BiosConfig::boot_order`](super::BiosConfig::boot_order) is non-empty, POST walks the
+//! list and boots the first entry whose media is present and bootable (valid MBR signature for
+//! disks/floppies, a valid El Torito catalog for CD-ROMs, a DHCP lease plus TFTP download for
+//! network boot). An empty list keeps the classic
+//! single-drive policy driven by [`BiosConfig::boot_drive`](super::BiosConfig::boot_drive).
+//!
+//! When [`BiosConfig::boot_menu`](super::BiosConfig::boot_menu) is set, POST instead renders a
+//! numbered menu of detected boot devices and parks the CPU. The host then calls
+//! [`Bios::poll_boot_menu`] as time passes; a digit key selects an entry, Enter (or the timeout)
+//! falls back to the configured order.
+
+use aero_cpu_core::state::CpuState;
+
+use super::post::CdromAsBlockDevice;
+use super::{set_real_mode_seg, Bios, BiosBus, BiosPostPhase, BlockDevice, CdromDevice, PxeNic};
+
+/// Default time the boot menu waits for a selection before using the configured boot order.
+pub const BOOT_MENU_DEFAULT_TIMEOUT_MS: u32 = 5_000;
+
+/// A boot device in the BIOS boot order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BootEntry {
+    /// BIOS fixed disk `index` (`DL=0x80 + index`).
+    Hdd(u8),
+    /// El Torito CD-ROM `index` (`DL=0xE0 + index`).
+    Cdrom(u8),
+    /// Floppy drive A: (`DL=0x00`).
+    Floppy,
+    /// PXE network boot via the machine's NIC (see `pxe.rs`).
+    Network,
+}
+
+impl BootEntry {
+    /// BIOS drive number passed in `DL` when booting this entry (`None` for network boot).
+    pub fn drive(self) -> Option<u8> {
+        match self {
+            BootEntry::Hdd(index) => Some(0x80 + index.min(0x5F)),
+            BootEntry::Cdrom(index) => Some(0xE0 + index.min(0x0F)),
+            BootEntry::Floppy => Some(0x00),
+            BootEntry::Network => None,
+        }
+    }
+
+    /// Map a BIOS drive number back to a boot entry.
+    pub fn from_drive(drive: u8) -> Option<Self> {
+        match drive {
+            0x00..=0x7F => Some(BootEntry::Floppy),
+            0x80..=0xDF => Some(BootEntry::Hdd(drive - 0x80)),
+            0xE0..=0xEF => Some(BootEntry::Cdrom(drive - 0xE0)),
+            _ => None,
+        }
+    }
+
+    /// Stable device-class code used by BIOS snapshots.
+    pub(super) fn class_code(self) -> u8 {
+        match self {
+            BootEntry::Hdd(_) => 0,
+            BootEntry::Cdrom(_) => 1,
+            BootEntry::Floppy => 2,
+            BootEntry::Network => 3,
+        }
+    }
+
+    pub(super) fn index(self) -> u8 {
+        match self {
+            BootEntry::Hdd(index) | BootEntry::Cdrom(index) => index,
+            BootEntry::Floppy | BootEntry::Network => 0,
+        }
+    }
+
+    pub(super) fn from_class_code(code: u8, index: u8) -> Option<Self> {
+        match code {
+            0 => Some(BootEntry::Hdd(index)),
+            1 => Some(BootEntry::Cdrom(index)),
+            2 => Some(BootEntry::Floppy),
+            3 => Some(BootEntry::Network),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            BootEntry::Hdd(index) => format!("Hard disk {index}"),
+            BootEntry::Cdrom(index) => format!("CD-ROM {index}"),
+            BootEntry::Floppy => "Floppy".to_string(),
+            BootEntry::Network => "Network (PXE)".to_string(),
+        }
+    }
+}
+
+/// State of an active boot menu (rendered, waiting for a selection).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootMenuState {
+    /// Menu entries in display order (key `1` selects `entries[0]`).
+    pub entries: Vec<BootEntry>,
+    /// Time left before falling back to the configured boot order.
+    pub remaining_ms: u64,
+}
+
+impl Bios {
+    /// Boot order used when no entry is explicitly selected, given whether a CD-ROM backend is
+    /// provided.
+    ///
+    /// With an empty [`BiosConfig::boot_order`](super::BiosConfig::boot_order) this mirrors the
+    /// classic policy: optional CD-first, then the configured `boot_drive`.
+    pub fn effective_boot_order(&self, cdrom_present: bool) -> Vec<BootEntry> {
+        if !self.config.boot_order.is_empty() {
+            return self.config.boot_order.clone();
+        }
+        let mut order = Vec::new();
+        if self.config.boot_from_cd_if_present && cdrom_present {
+            order.extend(BootEntry::from_drive(self.config.cd_boot_drive));
+        }
+        order.extend(BootEntry::from_drive(self.config.boot_drive));
+        order
+    }
+
+    /// `first` (if given) followed by the rest of the effective boot order.
+    fn boot_order_after(&self, first: Option<BootEntry>, cdrom_present: bool) -> Vec<BootEntry> {
+        let mut order: Vec<BootEntry> = first.into_iter().collect();
+        for entry in self.effective_boot_order(cdrom_present) {
+            if !order.contains(&entry) {
+                order.push(entry);
+            }
+        }
+        order
+    }
+
+    /// Try each entry of the boot order (after `first`, if given) until one boots.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn boot_in_order(
+        &mut self,
+        first: Option<BootEntry>,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        disk: &mut dyn BlockDevice,
+        mut floppy: Option<&mut dyn BlockDevice>,
+        mut cdrom: Option<&mut dyn CdromDevice>,
+        mut nic: Option<&mut dyn PxeNic>,
+    ) -> Result<(), &'static str> {
+        for entry in self.boot_order_after(first, cdrom.is_some()) {
+            match self.try_boot_entry(
+                entry,
+                cpu,
+                bus,
+                disk,
+                floppy.as_deref_mut().map(|floppy| floppy as _),
+                cdrom.as_deref_mut().map(|cdrom| cdrom as _),
+                nic.as_deref_mut().map(|nic| nic as _),
+            ) {
+                Ok(()) => return Ok(()),
+                Err(msg) => {
+                    let line = format!("Boot from {} failed: {msg}", entry.label());
+                    self.bios_diag(bus, &line);
+                }
+            }
+        }
+        Err("No bootable device found")
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn try_boot_entry(
+        &mut self,
+        entry: BootEntry,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        disk: &mut dyn BlockDevice,
+        floppy: Option<&mut dyn BlockDevice>,
+        cdrom: Option<&mut dyn CdromDevice>,
+        nic: Option<&mut dyn PxeNic>,
+    ) -> Result<(), &'static str> {
+        // Only one fixed disk is wired to the BIOS.
+        let media: Option<&mut dyn BlockDevice> = match entry {
+            BootEntry::Hdd(0) => Some(disk),
+            BootEntry::Hdd(_) => None,
+            BootEntry::Floppy => floppy.map(|floppy| floppy as _),
+            BootEntry::Cdrom(_) | BootEntry::Network => None,
+        };
+
+        // `boot_from_configured_device` keys off `config.boot_drive`; swap it only for this
+        // attempt so the host-configured policy is preserved (as for CD-first boot).
+        let configured_drive = self.config.boot_drive;
+        self.config.boot_drive = entry.drive().unwrap_or(configured_drive);
+        let res = match (entry, media, cdrom) {
+            (BootEntry::Network, _, _) => match nic {
+                Some(nic) => self.boot_from_network(cpu, bus, nic),
+                None => Err("Device not present"),
+            },
+            (BootEntry::Cdrom(_), _, Some(cdrom)) => {
+                let mut cd_disk = CdromAsBlockDevice::new(cdrom);
+                self.boot_from_configured_device(cpu, bus, &mut cd_disk)
+            }
+            (_, Some(media), _) => self.boot_from_configured_device(cpu, bus, media),
+            _ => Err("Device not present"),
+        };
+        self.config.boot_drive = configured_drive;
+
+        let (entry_cs, entry_ip) = res?;
+        set_real_mode_seg(&mut cpu.segments.cs, entry_cs);
+        cpu.set_rip(entry_ip as u64);
+        self.booted_entry = Some(entry);
+        Ok(())
+    }
+
+    /// Render the boot menu and park the CPU until [`Bios::poll_boot_menu`] picks a device.
+    pub(super) fn show_boot_menu(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        disk: &mut dyn BlockDevice,
+        floppy_present: bool,
+        cdrom_present: bool,
+        nic_present: bool,
+    ) {
+        let mut candidates = self.effective_boot_order(cdrom_present);
+        candidates.push(BootEntry::Floppy);
+        candidates.push(BootEntry::Hdd(0));
+        candidates.extend(BootEntry::from_drive(self.config.cd_boot_drive));
+        candidates.push(BootEntry::Network);
+
+        let mut entries: Vec<BootEntry> = Vec::new();
+        for entry in candidates {
+            let detected = match entry {
+                BootEntry::Hdd(0) => disk.size_in_sectors() > 0,
+                BootEntry::Hdd(_) => false,
+                BootEntry::Cdrom(_) => cdrom_present,
+                BootEntry::Floppy => floppy_present,
+                BootEntry::Network => nic_present,
+            };
+            if detected && !entries.contains(&entry) && entries.len() < 9 {
+                entries.push(entry);
+            }
+        }
+
+        self.bios_diag(bus, "Aero BIOS boot menu");
+        for (i, entry) in entries.iter().enumerate() {
+            let line = format!("  {}. {}", i + 1, entry.label());
+            self.bios_diag(bus, &line);
+        }
+        let prompt = format!(
+            "Select boot device (1-{}), or press Enter for the default order.",
+            entries.len()
+        );
+        self.bios_diag(bus, &prompt);
+
+        self.boot_menu = Some(BootMenuState {
+            entries,
+            remaining_ms: u64::from(self.config.boot_menu_timeout_ms),
+        });
+        cpu.halted = true;
+    }
+
+    /// Returns `true` while the boot menu is displayed and waiting for a selection.
+    pub fn boot_menu_active(&self) -> bool {
+        self.boot_menu.is_some()
+    }
+
+    /// Returns the active boot menu, if any.
+    pub fn boot_menu(&self) -> Option<&BootMenuState> {
+        self.boot_menu.as_ref()
+    }
+
+    /// First queued keystroke that leaves the boot menu: its queue position and the entry it
+    /// selects (`None` for Enter).
+    fn boot_menu_key(&self, menu: &BootMenuState) -> Option<(usize, Option<BootEntry>)> {
+        self.keyboard_queue
+            .iter()
+            .enumerate()
+            .find_map(|(pos, &key)| match key as u8 {
+                ascii @ b'1'..=b'9' => menu
+                    .entries
+                    .get(usize::from(ascii - b'1'))
+                    .map(|&entry| (pos, Some(entry))),
+                b'\r' => Some((pos, None)),
+                _ => None,
+            })
+    }
+
+    /// Boot order the next [`Bios::poll_boot_menu`] call with `elapsed_ms` would try, or `None`
+    /// if the menu keeps waiting.
+    ///
+    /// Lets callers lend devices that are costly to borrow (such as a PXE NIC) only to the poll
+    /// that actually boots from them.
+    pub fn pending_boot_menu_order(
+        &self,
+        elapsed_ms: u64,
+        cdrom_present: bool,
+    ) -> Option<Vec<BootEntry>> {
+        let menu = self.boot_menu.as_ref()?;
+        let selected = match self.boot_menu_key(menu) {
+            Some((_, selected)) => selected,
+            None if menu.remaining_ms <= elapsed_ms => None,
+            None => return None,
+        };
+        Some(self.boot_order_after(selected, cdrom_present))
+    }
+
+    /// Advance the boot menu by `elapsed_ms` and consume any pending keystrokes.
+    ///
+    /// Keys `1`-`9` boot the corresponding entry (falling back to the configured order if it is
+    /// not bootable); Enter or the timeout boots the configured order. Other keys are discarded.
+    ///
+    /// Returns `true` while the menu is still waiting.
+    #[allow(clippy::too_many_arguments)]
+    pub fn poll_boot_menu(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        disk: &mut dyn BlockDevice,
+        floppy: Option<&mut dyn BlockDevice>,
+        cdrom: Option<&mut dyn CdromDevice>,
+        nic: Option<&mut dyn PxeNic>,
+        elapsed_ms: u64,
+    ) -> bool {
+        let Some(menu) = self.boot_menu.as_ref() else {
+            return false;
+        };
+
+        // Keys up to the first one that leaves the menu are consumed; other keys are discarded.
+        let key = self.boot_menu_key(menu);
+        let consumed = key.map_or(self.keyboard_queue.len(), |(pos, _)| pos + 1);
+        self.keyboard_queue.drain(..consumed);
+        if consumed != 0 {
+            self.sync_keyboard_bda(bus);
+        }
+        let selected = match key {
+            Some((_, selected)) => selected,
+            None => {
+                let menu = self.boot_menu.as_mut().expect("boot menu is active");
+                menu.remaining_ms = menu.remaining_ms.saturating_sub(elapsed_ms);
+                if menu.remaining_ms != 0 {
+                    return true;
+                }
+                None
+            }
+        };
+
+        self.boot_menu = None;
+        cpu.halted = false;
+        self.post_code(BiosPostPhase::BootDeviceSelection);
+        match self.boot_in_order(selected, cpu, bus, disk, floppy, cdrom, nic) {
+            Ok(()) => self.post_code(BiosPostPhase::BootHandoff),
+            Err(msg) => self.bios_panic(cpu, bus, msg),
+        }
+        false
+    }
+}
@@ -0,0 +1,179 @@
+//! INT 13h Enhanced Disk Drive (EDD) services: the AH=48h drive parameter table.
+//!
+//! Table layouts follow the Phoenix EDD 3.0 spec (0x42 bytes, 8-byte device path) and the later
+//! T13 EDD-3 revision (0x4A bytes, 16-byte device path), as consumed by OS probes such as Linux's
+//! `edd_device_params` and GRUB.
+
+use super::BiosBus;
+
+/// EDD version reported by AH=41h (3.0).
+pub(super) const EDD_VERSION: u8 = 0x30;
+
+/// AH=41h support bitmap: fixed disk access subset (AH=42h-44h, 47h, 48h).
+pub(super) const EDD_SUPPORT_DISK_ACCESS: u16 = 1 << 0;
+/// AH=41h support bitmap: EDD subset (AH=48h device path information, AH=4Eh).
+pub(super) const EDD_SUPPORT_EDD: u16 = 1 << 2;
+
+/// AH=48h info flag: the default geometry fields are valid.
+pub(super) const EDD_INFO_CHS_VALID: u16 = 1 << 1;
+/// AH=48h info flag: the drive has removable media.
+pub(super) const EDD_INFO_REMOVABLE: u16 = 1 << 2;
+
+/// Smallest AH=48h table (EDD 1.x: geometry, sector count, bytes/sector).
+const EDD_PARAMS_V1_SIZE: usize = 0x1A;
+/// EDD 2.x table: adds the DPTE pointer.
+const EDD_PARAMS_V2_SIZE: usize = 0x1E;
+/// Phoenix EDD 3.0 table: adds device path information with an 8-byte device path.
+const EDD_PARAMS_V3_SIZE: usize = 0x42;
+/// T13 EDD-3 table: same as [`EDD_PARAMS_V3_SIZE`] with a 16-byte device path.
+const EDD_PARAMS_V3_T13_SIZE: usize = 0x4A;
+
+/// Offset of the device path information (`0xBEDD` key) in EDD 3.0 tables.
+const EDD_DPI_OFFSET: usize = 0x1E;
+/// Offset of the device path in EDD 3.0 tables.
+const EDD_DEVICE_PATH_OFFSET: usize = 0x38;
+
+/// Heads and sectors per track of the fixed disk CHS translation.
+///
+/// Shared by AH=08h, AH=48h and the IDENTIFY DEVICE data of the ATA device models (words 3/6).
+pub(super) const FIXED_DISK_HEADS: u8 = 16;
+pub(super) const FIXED_DISK_SECTORS_PER_TRACK: u8 = 63;
+/// Cylinder limit of the ATA default geometry (IDENTIFY word 1).
+const FIXED_DISK_MAX_CYLINDERS: u64 = 16383;
+
+/// Storage controller attachment of a BIOS drive, reported by INT 13h AH=48h as the EDD 3.0
+/// interface path (PCI bus/device/function) and device path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EddDevicePath {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub interface: EddInterface,
+}
+
+/// Interface of an [`EddDevicePath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EddInterface {
+    /// ATA disk on a legacy IDE channel (`channel` 0 = primary, 1 = secondary).
+    Ata { channel: u8, slave: bool },
+    /// ATAPI device on a legacy IDE channel.
+    Atapi { channel: u8, slave: bool },
+    /// Device on an AHCI port.
+    Sata { port: u8 },
+}
+
+impl EddInterface {
+    fn type_name(self) -> [u8; 8] {
+        match self {
+            Self::Ata { .. } => *b"ATA     ",
+            Self::Atapi { .. } => *b"ATAPI   ",
+            Self::Sata { .. } => *b"SATA    ",
+        }
+    }
+
+    /// Channel byte of the PCI interface path; `0xFF` for single-channel controllers.
+    fn channel(self) -> u8 {
+        match self {
+            Self::Ata { channel, .. } | Self::Atapi { channel, .. } => channel,
+            Self::Sata { .. } => 0xFF,
+        }
+    }
+
+    /// First byte of the device path: master/slave for IDE, port number for AHCI.
+    ///
+    /// The remaining bytes (ATAPI LUN, SATA port multiplier port) are always zero.
+    fn device(self) -> u8 {
+        match self {
+            Self::Ata { slave, .. } | Self::Atapi { slave, .. } => u8::from(slave),
+            Self::Sata { port } => port,
+        }
+    }
+}
+
+/// Contents of an AH=48h drive parameter table.
+pub(super) struct EddDriveParams {
+    pub info_flags: u16,
+    pub cylinders: u32,
+    pub heads: u32,
+    pub sectors_per_track: u32,
+    pub total_sectors: u64,
+    pub bytes_per_sector: u16,
+    /// Controller attachment; the interface and device paths are zero when unknown.
+    pub device_path: Option<EddDevicePath>,
+    /// Interface type string reported when `device_path` is unknown.
+    pub default_interface: [u8; 8],
+}
+
+/// Default CHS geometry of a fixed disk as reported by AH=48h, plus whether it is valid.
+///
+/// This matches the IDENTIFY DEVICE words 1/3/6 of the ATA device models. AH=08h reports the same
+/// heads and sectors per track with the legacy 1024-cylinder translation. Disks too large for the
+/// ATA default geometry report the maximum cylinder count with [`EDD_INFO_CHS_VALID`] clear.
+pub(super) fn fixed_disk_default_geometry(total_sectors: u64) -> (u32, u32, u32, bool) {
+    let heads = u64::from(FIXED_DISK_HEADS);
+    let spt = u64::from(FIXED_DISK_SECTORS_PER_TRACK);
+    let cylinders = (total_sectors / (heads * spt)).clamp(1, FIXED_DISK_MAX_CYLINDERS);
+    let valid = total_sectors <= FIXED_DISK_MAX_CYLINDERS * heads * spt;
+    (cylinders as u32, heads as u32, spt as u32, valid)
+}
+
+/// Write the AH=48h drive parameter table for a caller buffer of `buf_size` bytes.
+///
+/// Only the standard table sizes are ever returned (the largest one that fits), so the length
+/// word never claims fields we didn't fill. Returns `false` without touching guest memory if the
+/// buffer is smaller than the EDD 1.x table.
+pub(super) fn write_drive_params(
+    bus: &mut dyn BiosBus,
+    table_addr: u64,
+    buf_size: usize,
+    params: &EddDriveParams,
+) -> bool {
+    let len = if buf_size >= EDD_PARAMS_V3_T13_SIZE {
+        EDD_PARAMS_V3_T13_SIZE
+    } else if buf_size >= EDD_PARAMS_V3_SIZE {
+        EDD_PARAMS_V3_SIZE
+    } else if buf_size >= EDD_PARAMS_V2_SIZE {
+        EDD_PARAMS_V2_SIZE
+    } else if buf_size >= EDD_PARAMS_V1_SIZE {
+        EDD_PARAMS_V1_SIZE
+    } else {
+        return false;
+    };
+
+    let mut table = [0u8; EDD_PARAMS_V3_T13_SIZE];
+    table[0x00..0x02].copy_from_slice(&(len as u16).to_le_bytes());
+    table[0x02..0x04].copy_from_slice(&params.info_flags.to_le_bytes());
+    table[0x04..0x08].copy_from_slice(&params.cylinders.to_le_bytes());
+    table[0x08..0x0C].copy_from_slice(&params.heads.to_le_bytes());
+    table[0x0C..0x10].copy_from_slice(&params.sectors_per_track.to_le_bytes());
+    table[0x10..0x18].copy_from_slice(&params.total_sectors.to_le_bytes());
+    table[0x18..0x1A].copy_from_slice(&params.bytes_per_sector.to_le_bytes());
+    // No Device Parameter Table Extension: FFFFh:FFFFh.
+    table[0x1A..0x1E].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    if len >= EDD_PARAMS_V3_SIZE {
+        table[0x1E..0x20].copy_from_slice(&0xBEDDu16.to_le_bytes());
+        // Length of the device path information, from the key through the checksum.
+        table[0x20] = (len - EDD_DPI_OFFSET) as u8;
+        table[0x24..0x28].copy_from_slice(b"PCI ");
+        match params.device_path {
+            Some(path) => {
+                table[0x28..0x30].copy_from_slice(&path.interface.type_name());
+                table[0x30] = path.bus;
+                table[0x31] = path.device;
+                table[0x32] = path.function;
+                table[0x33] = path.interface.channel();
+                table[EDD_DEVICE_PATH_OFFSET] = path.interface.device();
+            }
+            None => table[0x28..0x30].copy_from_slice(&params.default_interface),
+        }
+        // The device path information (key .. checksum) sums to zero modulo 256.
+        let sum = table[EDD_DPI_OFFSET..len - 1]
+            .iter()
+            .fold(0u8, |acc, &b| acc.wrapping_add(b));
+        table[len - 1] = 0u8.wrapping_sub(sum);
+    }
+
+    bus.write_physical(table_addr, &table[..len]);
+    true
+}
@@ -1,7 +1,8 @@
 //! El Torito (bootable CD-ROM) support.
 //!
 //! This module implements enough of the El Torito and ISO9660 structures to locate and load the
-//! initial/default no-emulation boot entry from a typical Windows install ISO.
+//! initial/default boot entry from a typical Windows install ISO (no emulation) or an older
+//! driver/tool disc (1.2/1.44/2.88MB floppy emulation). Hard-disk emulation is rejected.
 
 use super::{BlockDevice, DiskError, BIOS_SECTOR_SIZE};
 
@@ -11,6 +12,7 @@ const ERR_NO_BOOT_RECORD: &str = "Missing El Torito boot record";
 const ERR_INVALID_CATALOG: &str = "invalid boot catalog validation entry";
 const ERR_NO_BOOTABLE_ENTRY: &str = "no bootable initial/default entry";
 const ERR_READ: &str = "boot image read error";
+const ERR_HDD_EMULATION: &str = "El Torito hard disk emulation is not supported";
 
 const ISO9660_STANDARD_IDENTIFIER: &[u8; 5] = b"CD001";
 const ISO9660_VERSION: u8 = 1;
@@ -47,6 +49,21 @@ const MAX_VOLUME_DESCRIPTOR_SCAN: u32 = 128;
 /// this case, and Windows install media commonly relies on this default for `etfsboot.com`.
 const DEFAULT_SECTOR_COUNT: u16 = 4;
 
+/// El Torito boot media type codes (boot entry byte 1, low nibble).
+pub(super) const MEDIA_NO_EMULATION: u8 = 0x00;
+const MEDIA_HARD_DISK: u8 = 0x04;
+
+/// Size in 512-byte sectors of the floppy image implied by an El Torito floppy-emulation media
+/// type, or `None` for no-emulation / hard-disk emulation.
+pub(super) fn emulated_floppy_sectors(media_type: u8) -> Option<u64> {
+    match media_type {
+        0x01 => Some(2400), // 1.2 MiB
+        0x02 => Some(2880), // 1.44 MiB
+        0x03 => Some(5760), // 2.88 MiB
+        _ => None,
+    }
+}
+
 /// Fields needed to load and jump to a no-emulation El Torito boot image.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) struct BootImageInfo {
@@ -58,6 +75,8 @@ pub(super) struct BootImageInfo {
     pub(super) sector_count: u16,
     /// 2048-byte logical block address of the boot image in the ISO.
     pub(super) load_rba: u32,
+    /// El Torito boot media type (`0` = no emulation, `1..=3` = floppy emulation).
+    pub(super) media_type: u8,
 }
 
 /// Parsed/default El Torito boot image selection.
@@ -65,7 +84,7 @@ pub(super) struct BootImageInfo {
 pub(super) struct ParsedBootImage {
     /// 2048-byte logical block address of the El Torito boot catalog.
     pub(super) boot_catalog_lba: u32,
-    /// Initial/default boot image entry fields (no-emulation).
+    /// Initial/default boot image entry fields.
     pub(super) image: BootImageInfo,
 }
 
@@ -74,15 +93,20 @@ pub(super) fn parse_boot_image(
 ) -> Result<ParsedBootImage, &'static str> {
     let boot_catalog_lba = find_boot_catalog_lba(disk)?;
     let image = parse_boot_catalog(disk, boot_catalog_lba)?;
+    if image.media_type == MEDIA_HARD_DISK {
+        return Err(ERR_HDD_EMULATION);
+    }
 
     // Validate that the boot image fits within the underlying disk. This is a hard safety check so
-    // we do not attempt out-of-range reads on corrupt catalogs.
+    // we do not attempt out-of-range reads on corrupt catalogs. For floppy emulation the whole
+    // emulated diskette must be present, since INT 13h may read any of its sectors.
     let start_lba_512 = u64::from(image.load_rba)
         .checked_mul(BIOS_SECTORS_PER_ISO_BLOCK)
         .ok_or(ERR_READ)?;
-    let end_lba_512 = start_lba_512
-        .checked_add(u64::from(image.sector_count))
-        .ok_or(ERR_READ)?;
+    let image_sectors = emulated_floppy_sectors(image.media_type)
+        .unwrap_or(0)
+        .max(u64::from(image.sector_count));
+    let end_lba_512 = start_lba_512.checked_add(image_sectors).ok_or(ERR_READ)?;
     if end_lba_512 > disk.size_in_sectors() {
         return Err(ERR_READ);
     }
@@ -192,8 +216,8 @@ fn parse_boot_catalog(
                     continue;
                 }
 
-                // Bootable + no-emulation.
-                if entry[0] != 0x88 || entry[1] != 0 {
+                // Bootable + a known media type (no emulation, floppy or hard-disk emulation).
+                if entry[0] != 0x88 || entry[1] & 0x0F > MEDIA_HARD_DISK {
                     continue;
                 }
 
@@ -240,8 +264,9 @@ fn parse_boot_entry(entry: &[u8], boot_catalog_lba: u32) -> Result<BootImageInfo
         return Err(ERR_NO_BOOTABLE_ENTRY);
     }
 
-    let media_type = entry[1];
-    if media_type != 0 {
+    // The high nibble carries flags (continuation/ATAPI/SCSI); only the low nibble is the type.
+    let media_type = entry[1] & 0x0F;
+    if media_type > MEDIA_HARD_DISK {
         return Err(ERR_NO_BOOTABLE_ENTRY);
     }
 
@@ -260,10 +285,11 @@ fn parse_boot_entry(entry: &[u8], boot_catalog_lba: u32) -> Result<BootImageInfo
         load_segment
     };
     let sector_count = u16::from_le_bytes(entry[6..8].try_into().unwrap());
-    let sector_count = if sector_count == 0 {
-        DEFAULT_SECTOR_COUNT
-    } else {
-        sector_count
+    let sector_count = match (sector_count, media_type) {
+        // Emulated media boot like a real diskette: only the boot sector is loaded.
+        (0, MEDIA_NO_EMULATION) => DEFAULT_SECTOR_COUNT,
+        (0, _) => 1,
+        (n, _) => n,
     };
     let load_rba = u32::from_le_bytes(entry[8..12].try_into().unwrap());
 
@@ -272,6 +298,7 @@ fn parse_boot_entry(entry: &[u8], boot_catalog_lba: u32) -> Result<BootImageInfo
         load_segment,
         sector_count,
         load_rba,
+        media_type,
     })
 }
 
@@ -438,6 +465,7 @@ mod tests {
                 load_segment: 0x07C0,
                 sector_count: 4,
                 load_rba: boot_image_lba,
+                media_type: MEDIA_NO_EMULATION,
             }
         );
     }
@@ -468,6 +496,7 @@ mod tests {
                 load_segment: 0x07C0,
                 sector_count: 4,
                 load_rba: boot_image_lba,
+                media_type: MEDIA_NO_EMULATION,
             }
         );
     }
@@ -669,6 +698,51 @@ mod tests {
         assert_eq!(parsed.image.sector_count, DEFAULT_SECTOR_COUNT);
     }
 
+    #[test]
+    fn parse_boot_image_accepts_floppy_emulation_and_requires_whole_image() {
+        let boot_catalog_lba: u32 = 20;
+        let boot_image_lba: u32 = 21;
+        let boot_image = [0xAB; ISO_BLOCK_BYTES];
+        let mut img =
+            build_minimal_iso_no_emulation(boot_catalog_lba, boot_image_lba, &boot_image, 0, 0);
+        // Patch the initial entry to 1.44MB floppy emulation.
+        img[boot_catalog_lba as usize * ISO_BLOCK_BYTES + 32 + 1] = 0x02;
+
+        // The ISO only holds the first block of the 1.44MB image.
+        let mut disk = InMemoryDisk::new(img.clone());
+        assert_eq!(parse_boot_image(&mut disk).unwrap_err(), ERR_READ);
+
+        img.resize(
+            (boot_image_lba as usize) * ISO_BLOCK_BYTES + 2880 * BIOS_SECTOR_SIZE,
+            0,
+        );
+        let mut disk = InMemoryDisk::new(img);
+        let parsed = parse_boot_image(&mut disk).expect("parser should succeed");
+        assert_eq!(
+            parsed.image,
+            BootImageInfo {
+                boot_catalog_lba,
+                load_segment: DEFAULT_LOAD_SEGMENT,
+                sector_count: 1,
+                load_rba: boot_image_lba,
+                media_type: 0x02,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_boot_image_rejects_hard_disk_emulation() {
+        let boot_catalog_lba: u32 = 20;
+        let boot_image_lba: u32 = 21;
+        let boot_image = [0xAB; ISO_BLOCK_BYTES];
+        let mut img =
+            build_minimal_iso_no_emulation(boot_catalog_lba, boot_image_lba, &boot_image, 0, 1);
+        img[boot_catalog_lba as usize * ISO_BLOCK_BYTES + 32 + 1] = MEDIA_HARD_DISK;
+
+        let mut disk = InMemoryDisk::new(img);
+        assert_eq!(parse_boot_image(&mut disk).unwrap_err(), ERR_HDD_EMULATION);
+    }
+
     #[test]
     fn parse_boot_image_scans_catalog_for_later_bootable_entry() {
         let boot_catalog_lba: u32 = 20;
@@ -752,6 +826,7 @@ mod tests {
                 load_segment: DEFAULT_LOAD_SEGMENT,
                 sector_count: 4,
                 load_rba: boot_image_lba,
+                media_type: MEDIA_NO_EMULATION,
             }
         );
     }
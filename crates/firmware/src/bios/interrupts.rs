@@ -2,11 +2,12 @@ use aero_cpu_core::state::{
     gpr, mask_bits, CpuState, FLAG_CF, FLAG_DF, FLAG_OF, FLAG_PF, FLAG_SF, FLAG_ZF,
 };
 
+use super::edd::{self, EddDriveParams, EDD_INFO_CHS_VALID, EDD_INFO_REMOVABLE};
 use super::{
-    disk_err_to_int13_status, set_real_mode_seg, Bios, BiosBus, BiosMemoryBus, BlockDevice,
-    CdromDevice, DiskError, ElToritoBootMediaType, BDA_BASE, BDA_KEYBOARD_BUF_HEAD_OFFSET,
-    BDA_KEYBOARD_BUF_START, BDA_KEYBOARD_BUF_TAIL_OFFSET, BIOS_SECTOR_SIZE, BIOS_SEGMENT,
-    CDROM_SECTOR_SIZE, DISKETTE_PARAM_TABLE_OFFSET, EBDA_BASE, EBDA_SIZE,
+    disk_err_to_int13_status, set_real_mode_seg, Bios, BiosBus, BiosMemoryBus, BiosPostPhase,
+    BlockDevice, CdromDevice, DiskError, ElToritoBootMediaType, BDA_BASE,
+    BDA_KEYBOARD_BUF_HEAD_OFFSET, BDA_KEYBOARD_BUF_START, BDA_KEYBOARD_BUF_TAIL_OFFSET,
+    BIOS_SECTOR_SIZE, BIOS_SEGMENT, CDROM_SECTOR_SIZE, DISKETTE_PARAM_TABLE_OFFSET,
     FIXED_DISK_PARAM_TABLE_OFFSET, KEYBOARD_QUEUE_CAPACITY,
 };
 use crate::cpu::CpuState as FirmwareCpuState;
@@ -67,6 +68,27 @@ impl BlockDevice for CdromAsBlockDevice<'_> {
     }
 }
 
+/// View of an El Torito floppy-emulation boot image (a contiguous run of 512-byte sectors on the
+/// CD) as the emulated diskette.
+struct ElToritoEmulatedFloppy<'a> {
+    iso: &'a mut dyn BlockDevice,
+    start_lba: u64,
+    sectors: u64,
+}
+
+impl BlockDevice for ElToritoEmulatedFloppy<'_> {
+    fn read_sector(&mut self, lba: u64, buf: &mut [u8; BIOS_SECTOR_SIZE]) -> Result<(), DiskError> {
+        if lba >= self.sectors {
+            return Err(DiskError::OutOfRange);
+        }
+        self.iso.read_sector(self.start_lba + lba, buf)
+    }
+
+    fn size_in_sectors(&self) -> u64 {
+        self.sectors
+    }
+}
+
 pub fn dispatch_interrupt(
     bios: &mut Bios,
     vector: u8,
@@ -328,6 +350,7 @@ fn handle_int19(
     // Load the configured boot device (MBR or El Torito CD) into RAM and initialize registers,
     // matching POST's boot conventions.
     let boot_drive = bios.config.boot_drive;
+    bios.post_code(BiosPostPhase::BootDeviceSelection);
     let boot_result = if (0xE0..=0xEF).contains(&boot_drive) {
         if let Some(cdrom) = cdrom {
             let mut cd_disk = CdromAsBlockDevice::new(cdrom);
@@ -346,6 +369,7 @@ fn handle_int19(
             return;
         }
     };
+    bios.post_code(BiosPostPhase::BootHandoff);
 
     // Use a clean 0000:7C00 stack. We must set SP to 7BFA so the following IRET lands with
     // SP=7C00.
@@ -405,6 +429,41 @@ fn handle_int13(
 ) {
     let ah = ((cpu.gpr[gpr::RAX] >> 8) & 0xFF) as u8;
     let drive = (cpu.gpr[gpr::RDX] & 0xFF) as u8;
+
+    // El Torito floppy emulation: the emulated drive is backed by the boot image on the CD (read
+    // through the CD-ROM backend when present, else the raw ISO exposed as `disk`) instead of any
+    // physical floppy.
+    if ah != 0x4B {
+        if let Some((start_lba, sectors)) = bios.el_torito_emulated_floppy(drive) {
+            let mut cd_disk = cdrom
+                .as_mut()
+                .map(|cdrom| CdromAsBlockDevice::new(&mut **cdrom));
+            let iso: &mut dyn BlockDevice = match cd_disk.as_mut() {
+                Some(cd_disk) => cd_disk,
+                None => disk,
+            };
+            let mut image = ElToritoEmulatedFloppy {
+                iso,
+                start_lba,
+                sectors,
+            };
+            handle_int13_drive(bios, cpu, bus, &mut image, None);
+            return;
+        }
+    }
+
+    handle_int13_drive(bios, cpu, bus, disk, cdrom);
+}
+
+fn handle_int13_drive(
+    bios: &mut Bios,
+    cpu: &mut CpuState,
+    bus: &mut dyn BiosBus,
+    disk: &mut dyn BlockDevice,
+    mut cdrom: Option<&mut dyn CdromDevice>,
+) {
+    let ah = ((cpu.gpr[gpr::RAX] >> 8) & 0xFF) as u8;
+    let drive = (cpu.gpr[gpr::RDX] & 0xFF) as u8;
     let cdrom_present = cdrom.is_some();
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -460,6 +519,7 @@ fn handle_int13(
         if drive < 0x80 {
             // Floppy disk (heuristic by media size; fallback is a reasonable default).
             match total_sectors {
+                5760 => (80, 2, 36), // 2.88 MiB (3.5")
                 2880 => (80, 2, 18), // 1.44 MiB (3.5")
                 2400 => (80, 2, 15), // 1.2 MiB (5.25")
                 1440 => (80, 2, 9),  // 720 KiB (3.5")
@@ -476,7 +536,11 @@ fn handle_int13(
             }
         } else {
             // Fixed disk (minimal geometry; matches legacy tests + common boot expectations).
-            (1024, 16, 63)
+            (
+                1024,
+                edd::FIXED_DISK_HEADS,
+                edd::FIXED_DISK_SECTORS_PER_TRACK,
+            )
         }
     }
 
@@ -533,10 +597,23 @@ fn handle_int13(
                     bus.write_u32(packet_addr + 8, info.boot_catalog_lba.unwrap_or(0));
                     bus.write_u16(packet_addr + 12, info.load_segment.unwrap_or(0));
                     bus.write_u16(packet_addr + 14, info.sector_count.unwrap_or(0));
-                    // Reserved bytes.
-                    bus.write_u8(packet_addr + 16, 0);
-                    bus.write_u8(packet_addr + 17, 0);
-                    bus.write_u8(packet_addr + 18, 0);
+                    // Emulated diskette geometry (max cylinder / sectors per track / max head, in
+                    // INT 13h AH=08h CHS encoding); zero in no-emulation mode.
+                    let (cyl, spt, head) = match info.media_type.emulated_floppy_sectors() {
+                        Some(sectors) => {
+                            let (cylinders, heads, spt) = geometry_for_drive(0x00, sectors);
+                            let max_cyl = cylinders - 1;
+                            (
+                                (max_cyl & 0xFF) as u8,
+                                (spt & 0x3F) | (((max_cyl >> 2) as u8) & 0xC0),
+                                heads - 1,
+                            )
+                        }
+                        None => (0, 0, 0),
+                    };
+                    bus.write_u8(packet_addr + 16, cyl);
+                    bus.write_u8(packet_addr + 17, spt);
+                    bus.write_u8(packet_addr + 18, head);
 
                     bios.last_int13_status = 0;
                     cpu.rflags &= !FLAG_CF;
@@ -679,20 +756,14 @@ fn handle_int13(
                 if (cpu.gpr[gpr::RBX] & 0xFFFF) == 0x55AA {
                     // EDD extensions installation check.
                     //
-                    // Strategy A: We advertise EDD 3.0 (AH=0x30) and implement the 0x42-byte
-                    // "Drive Parameter Table" returned by AH=48h when the caller provides a large
-                    // enough buffer.
-                    //
-                    // Some bootloaders/OS probes treat a mismatch between the reported EDD version
-                    // (AH=41h) and the returned AH=48h table size as a BIOS bug and will refuse to
-                    // use EDD services.
-                    cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | (0x30u64 << 8);
+                    // See the fixed disk handler for the version/bitmap rationale. The removable
+                    // media subset (bit 1: AH=45h/46h/49h lock, eject and media change) is not
+                    // implemented, so it is not advertised for CD-ROM drives either.
+                    cpu.gpr[gpr::RAX] =
+                        (cpu.gpr[gpr::RAX] & !0xFFFF) | (u64::from(edd::EDD_VERSION) << 8);
                     cpu.gpr[gpr::RBX] = (cpu.gpr[gpr::RBX] & !0xFFFF) | 0xAA55;
-                    // Feature bitmap (Phoenix EDD spec):
-                    // - bit 0: functions 42h-44h supported (extended read/write/verify)
-                    // - bit 1: functions 45h-47h supported (lock/eject/seek)
-                    // - bit 2: function 48h supported (drive parameter table)
-                    cpu.gpr[gpr::RCX] = (cpu.gpr[gpr::RCX] & !0xFFFF) | 0x0005;
+                    cpu.gpr[gpr::RCX] = (cpu.gpr[gpr::RCX] & !0xFFFF)
+                        | u64::from(edd::EDD_SUPPORT_DISK_ACCESS | edd::EDD_SUPPORT_EDD);
                     bios.last_int13_status = 0;
                     cpu.rflags &= !FLAG_CF;
                 } else {
@@ -855,6 +926,37 @@ fn handle_int13(
                 cpu.rflags &= !FLAG_CF;
                 cpu.gpr[gpr::RAX] &= !0xFF00u64;
             }
+            0x47 => {
+                // Extended seek via Disk Address Packet (EDD).
+                //
+                // CD-ROM reads are synchronous, so this only validates the target ISO LBA.
+                if !drive_present(bios, bus, drive, cdrom_present) {
+                    set_error(bios, cpu, 0x01);
+                    return;
+                }
+
+                let si = cpu.gpr[gpr::RSI] & 0xFFFF;
+                let dap_addr = cpu.apply_a20(cpu.segments.ds.base.wrapping_add(si));
+                let dap_size = bus.read_u8(dap_addr);
+                if dap_size != 0x10 && dap_size != 0x18 {
+                    set_error(bios, cpu, 0x01);
+                    return;
+                }
+                let lba_2048 = bus.read_u64(dap_addr + 8);
+
+                let total_2048 = cdrom
+                    .as_deref()
+                    .map(|cdrom| cdrom.size_in_sectors())
+                    .unwrap_or_else(|| disk.size_in_sectors() / 4);
+                if lba_2048 >= total_2048 {
+                    set_error(bios, cpu, 0x04);
+                    return;
+                }
+
+                bios.last_int13_status = 0;
+                cpu.rflags &= !FLAG_CF;
+                cpu.gpr[gpr::RAX] &= !0xFF00u64;
+            }
             0x48 => {
                 // Extended get drive parameters (EDD) for CD-ROM media (2048-byte sectors).
                 if !drive_present(bios, bus, drive, cdrom_present) {
@@ -869,98 +971,41 @@ fn handle_int13(
                 let si = cpu.gpr[gpr::RSI] & 0xFFFF;
                 let table_addr = cpu.apply_a20(cpu.segments.ds.base.wrapping_add(si));
                 let buf_size = bus.read_u16(table_addr) as usize;
-                const EDD_PARAMS_MIN_SIZE: usize = 0x1A;
-                const EDD_PARAMS_V2_SIZE: usize = 0x1E;
-                const EDD_PARAMS_V3_SIZE: usize = 0x42;
-                if buf_size < EDD_PARAMS_MIN_SIZE {
+                let params = EddDriveParams {
+                    info_flags: EDD_INFO_REMOVABLE,
+                    // CD media has no CHS geometry (`EDD_INFO_CHS_VALID` is clear).
+                    cylinders: 0,
+                    heads: 0,
+                    sectors_per_track: 0,
+                    total_sectors: total_2048,
+                    bytes_per_sector: CDROM_SECTOR_SIZE as u16,
+                    device_path: bios.config.cdrom_device_path,
+                    default_interface: *b"ATAPI   ",
+                };
+                if !edd::write_drive_params(bus, table_addr, buf_size, &params) {
                     set_error(bios, cpu, 0x01);
                     return;
                 }
 
-                // Fill the EDD drive parameter table.
-                //
-                // EDD 3.0 defines a 0x42-byte structure. For compatibility with older callers we
-                // also support (and may return) smaller table sizes (down to 0x1A bytes).
-                //
-                // To avoid claiming a size we didn't fully populate, we only ever return one of
-                // the standard EDD structure sizes:
-                // - 0x42 (EDD 3.0) when the caller provides >= 0x42 bytes
-                // - 0x1E (EDD 2.x) when the caller provides >= 0x1E bytes but < 0x42
-                // - 0x1A (EDD 1.1) when the caller provides 0x1A..0x1D bytes
-                let write_len = if buf_size >= EDD_PARAMS_V3_SIZE {
-                    EDD_PARAMS_V3_SIZE
-                } else if buf_size >= EDD_PARAMS_V2_SIZE {
-                    EDD_PARAMS_V2_SIZE
-                } else {
-                    EDD_PARAMS_MIN_SIZE
-                };
-                bus.write_u16(table_addr, write_len as u16);
-
-                if write_len >= 4 {
-                    bus.write_u16(table_addr + 2, 0); // flags
-                }
-                if write_len >= 8 {
-                    bus.write_u32(table_addr + 4, 1024); // cylinders (placeholder)
-                }
-                if write_len >= 12 {
-                    bus.write_u32(table_addr + 8, 16); // heads (placeholder)
-                }
-                if write_len >= 16 {
-                    bus.write_u32(table_addr + 12, 63); // sectors/track (placeholder)
-                }
-                if write_len >= 24 {
-                    bus.write_u64(table_addr + 16, total_2048);
-                }
-                if write_len >= 26 {
-                    bus.write_u16(table_addr + 24, 2048); // bytes/sector
-                }
-                if write_len >= 30 {
-                    bus.write_u32(table_addr + 26, 0); // DPTE pointer (unused)
+                bios.last_int13_status = 0;
+                cpu.rflags &= !FLAG_CF;
+                cpu.gpr[gpr::RAX] &= !0xFF00u64;
+            }
+            0x4E => {
+                // Set hardware configuration (EDD); see the fixed disk handler.
+                if !drive_present(bios, bus, drive, cdrom_present) {
+                    set_error(bios, cpu, 0x01);
+                    return;
                 }
-
-                if write_len >= EDD_PARAMS_V3_SIZE {
-                    // EDD 3.0 drive parameter table extension.
-                    //
-                    // Offsets and layout follow the Phoenix EDD 3.0 spec, as implemented by common
-                    // BIOSes and consumed by OS probes (e.g. Linux's `edd_device_params`).
-                    let interface_type: [u8; 8] = *b"ATAPI   ";
-
-                    bus.write_u16(table_addr + 0x1E, 0xBEDD); // key
-                    bus.write_u8(table_addr + 0x20, 0x1E); // device path info length
-                    bus.write_u8(table_addr + 0x21, 0x00); // reserved
-                    bus.write_u16(table_addr + 0x22, 0x0000); // reserved
-
-                    // Host bus type (4 bytes) and interface type (8 bytes) strings.
-                    for (i, b) in b"PCI ".iter().copied().enumerate() {
-                        bus.write_u8(table_addr + 0x24 + i as u64, b);
-                    }
-                    for (i, b) in interface_type.iter().copied().enumerate() {
-                        bus.write_u8(table_addr + 0x28 + i as u64, b);
-                    }
-
-                    // interface_path (8), device_path (8) and reserved (1) are all zero.
-                    for off in 0x30u64..0x40 {
-                        bus.write_u8(table_addr + off, 0);
-                    }
-                    bus.write_u8(table_addr + 0x40, 0);
-
-                    // Compute the 8-bit checksum so that the sum of the device path info bytes
-                    // (host bus type .. checksum) is 0 modulo 256.
-                    let mut sum: u8 = 0;
-                    for off in 0x24..0x42 {
-                        // Skip checksum byte while summing; it's at the last byte (0x41).
-                        if off == 0x41 {
-                            continue;
-                        }
-                        sum = sum.wrapping_add(bus.read_u8(table_addr + off));
-                    }
-                    let checksum = (0u8).wrapping_sub(sum);
-                    bus.write_u8(table_addr + 0x41, checksum);
+                let al = (cpu.gpr[gpr::RAX] & 0xFF) as u8;
+                if !(0x01..=0x07).contains(&al) {
+                    set_error(bios, cpu, 0x01);
+                    return;
                 }
 
                 bios.last_int13_status = 0;
                 cpu.rflags &= !FLAG_CF;
-                cpu.gpr[gpr::RAX] &= !0xFF00u64;
+                cpu.gpr[gpr::RAX] &= !0xFFFFu64;
             }
             _ => {
                 // Legacy CHS functions are not supported for CD-ROM drives; extensions are
@@ -1333,20 +1378,23 @@ fn handle_int13(
             if (cpu.gpr[gpr::RBX] & 0xFFFF) == 0x55AA && drive >= 0x80 {
                 // EDD extensions installation check.
                 //
-                // Strategy A: We advertise EDD 3.0 (AH=0x30) and implement the 0x42-byte
-                // "Drive Parameter Table" returned by AH=48h when the caller provides a large
+                // We advertise EDD 3.0 (AH=0x30) and return the EDD 3.0 drive parameter table
+                // (with device path information) from AH=48h when the caller provides a large
                 // enough buffer.
                 //
                 // Some bootloaders/OS probes treat a mismatch between the reported EDD version
                 // (AH=41h) and the returned AH=48h table size as a BIOS bug and will refuse to
                 // use EDD services.
-                cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | (0x30u64 << 8);
+                cpu.gpr[gpr::RAX] =
+                    (cpu.gpr[gpr::RAX] & !0xFFFF) | (u64::from(edd::EDD_VERSION) << 8);
                 cpu.gpr[gpr::RBX] = (cpu.gpr[gpr::RBX] & !0xFFFF) | 0xAA55;
-                // Feature bitmap (Phoenix EDD spec):
-                // - bit 0: functions 42h-44h supported (extended read/write/verify)
-                // - bit 1: functions 45h-47h supported (lock/eject/seek)
-                // - bit 2: function 48h supported (drive parameter table)
-                cpu.gpr[gpr::RCX] = (cpu.gpr[gpr::RCX] & !0xFFFF) | 0x0005;
+                // Interface support bitmap (EDD 3.0), advertising only subsets we implement in
+                // full:
+                // - bit 0: fixed disk access (AH=42h-44h, 47h, 48h)
+                // - bit 1: drive locking and ejecting (AH=45h, 46h, 49h) - not implemented
+                // - bit 2: EDD support (AH=48h device path information, AH=4Eh)
+                cpu.gpr[gpr::RCX] = (cpu.gpr[gpr::RCX] & !0xFFFF)
+                    | u64::from(edd::EDD_SUPPORT_DISK_ACCESS | edd::EDD_SUPPORT_EDD);
                 bios.last_int13_status = 0;
                 cpu.rflags &= !FLAG_CF;
             } else {
@@ -1482,11 +1530,10 @@ fn handle_int13(
             cpu.rflags &= !FLAG_CF;
             cpu.gpr[gpr::RAX] &= !0xFF00u64;
         }
-        0x48 => {
-            // Extended get drive parameters (EDD).
+        0x47 => {
+            // Extended seek via Disk Address Packet (EDD).
             //
-            // DS:SI points to a caller-supplied buffer; the first WORD is the
-            // buffer size in bytes.
+            // Disk I/O is synchronous, so this only validates the target LBA.
             if !drive_present(bios, bus, drive, cdrom_present) {
                 set_error(bios, cpu, 0x01);
                 return;
@@ -1497,108 +1544,95 @@ fn handle_int13(
             }
 
             let si = cpu.gpr[gpr::RSI] & 0xFFFF;
-            let table_addr = cpu.apply_a20(cpu.segments.ds.base.wrapping_add(si));
-            let buf_size = bus.read_u16(table_addr) as usize;
-            const EDD_PARAMS_MIN_SIZE: usize = 0x1A;
-            const EDD_PARAMS_V2_SIZE: usize = 0x1E;
-            const EDD_PARAMS_V3_SIZE: usize = 0x42;
-            if buf_size < EDD_PARAMS_MIN_SIZE {
+            let dap_addr = cpu.apply_a20(cpu.segments.ds.base.wrapping_add(si));
+            let dap_size = bus.read_u8(dap_addr);
+            if dap_size != 0x10 && dap_size != 0x18 {
                 set_error(bios, cpu, 0x01);
                 return;
             }
+            let lba = bus.read_u64(dap_addr + 8);
+            if lba >= disk.size_in_sectors() {
+                set_error(bios, cpu, 0x04);
+                return;
+            }
 
-            // Fill the EDD drive parameter table.
-            //
-            // EDD 3.0 defines a 0x42-byte structure. For compatibility with older callers we
-            // also support (and may return) smaller table sizes (down to 0x1A bytes).
+            bios.last_int13_status = 0;
+            cpu.rflags &= !FLAG_CF;
+            cpu.gpr[gpr::RAX] &= !0xFF00u64;
+        }
+        0x48 => {
+            // Extended get drive parameters (EDD).
             //
-            // To avoid claiming a size we didn't fully populate, we only ever return one of the
-            // standard EDD structure sizes:
-            // - 0x42 (EDD 3.0) when the caller provides >= 0x42 bytes
-            // - 0x1E (EDD 2.x) when the caller provides >= 0x1E bytes but < 0x42
-            // - 0x1A (EDD 1.1) when the caller provides 0x1A..0x1D bytes
-            let write_len = if buf_size >= EDD_PARAMS_V3_SIZE {
-                EDD_PARAMS_V3_SIZE
-            } else if buf_size >= EDD_PARAMS_V2_SIZE {
-                EDD_PARAMS_V2_SIZE
-            } else {
-                EDD_PARAMS_MIN_SIZE
-            };
-            bus.write_u16(table_addr, write_len as u16);
-
-            if write_len >= 4 {
-                bus.write_u16(table_addr + 2, 0); // flags
-            }
-            if write_len >= 8 {
-                bus.write_u32(table_addr + 4, 1024); // cylinders
-            }
-            if write_len >= 12 {
-                bus.write_u32(table_addr + 8, 16); // heads
-            }
-            if write_len >= 16 {
-                bus.write_u32(table_addr + 12, 63); // sectors/track
-            }
-            if write_len >= 24 {
-                bus.write_u64(table_addr + 16, disk.size_in_sectors());
-            }
-            if write_len >= 26 {
-                bus.write_u16(table_addr + 24, BIOS_SECTOR_SIZE as u16); // bytes/sector
+            // DS:SI points to a caller-supplied buffer; the first WORD is the
+            // buffer size in bytes. We return the largest standard table that fits: EDD 1.x
+            // (0x1A), 2.x (0x1E), Phoenix EDD 3.0 (0x42) or T13 EDD-3 (0x4A), the latter two with
+            // the PCI path of the controller backing the drive.
+            if !drive_present(bios, bus, drive, cdrom_present) {
+                set_error(bios, cpu, 0x01);
+                return;
             }
-            if write_len >= 30 {
-                bus.write_u32(table_addr + 26, 0); // DPTE pointer (unused)
+            if drive < 0x80 {
+                set_error(bios, cpu, 0x01);
+                return;
             }
 
-            if write_len >= EDD_PARAMS_V3_SIZE {
-                // EDD 3.0 drive parameter table extension.
-                //
-                // Offsets and layout follow the Phoenix EDD 3.0 spec, as implemented by common
-                // BIOSes and consumed by OS probes (e.g. Linux's `edd_device_params`).
-                //
-                // We fill only identification strings; the interface/device path fields are left
-                // as zeroes (sufficient for most callers).
-                let interface_type: [u8; 8] = if drive >= 0xE0 {
+            let si = cpu.gpr[gpr::RSI] & 0xFFFF;
+            let table_addr = cpu.apply_a20(cpu.segments.ds.base.wrapping_add(si));
+            let buf_size = bus.read_u16(table_addr) as usize;
+            let total_sectors = disk.size_in_sectors();
+            let (cylinders, heads, sectors_per_track, chs_valid) =
+                edd::fixed_disk_default_geometry(total_sectors);
+            let params = EddDriveParams {
+                info_flags: if chs_valid { EDD_INFO_CHS_VALID } else { 0 },
+                cylinders,
+                heads,
+                sectors_per_track,
+                total_sectors,
+                bytes_per_sector: BIOS_SECTOR_SIZE as u16,
+                device_path: if drive == 0x80 {
+                    bios.config.hdd_device_path
+                } else {
+                    None
+                },
+                default_interface: if drive >= 0xE0 {
                     *b"ATAPI   "
                 } else {
                     *b"ATA     "
-                };
-
-                bus.write_u16(table_addr + 0x1E, 0xBEDD); // key
-                bus.write_u8(table_addr + 0x20, 0x1E); // device path info length
-                bus.write_u8(table_addr + 0x21, 0x00); // reserved
-                bus.write_u16(table_addr + 0x22, 0x0000); // reserved
-
-                // Host bus type (4 bytes) and interface type (8 bytes) strings.
-                for (i, b) in b"PCI ".iter().copied().enumerate() {
-                    bus.write_u8(table_addr + 0x24 + i as u64, b);
-                }
-                for (i, b) in interface_type.iter().copied().enumerate() {
-                    bus.write_u8(table_addr + 0x28 + i as u64, b);
-                }
-
-                // interface_path (8), device_path (8) and reserved (1) are all zero.
-                for off in 0x30u64..0x40 {
-                    bus.write_u8(table_addr + off, 0);
-                }
-                bus.write_u8(table_addr + 0x40, 0);
-
-                // Compute the 8-bit checksum so that the sum of the device path info bytes
-                // (host bus type .. checksum) is 0 modulo 256.
-                let mut sum: u8 = 0;
-                for off in 0x24..0x42 {
-                    // Skip checksum byte while summing; it's at the last byte (0x41).
-                    if off == 0x41 {
-                        continue;
-                    }
-                    sum = sum.wrapping_add(bus.read_u8(table_addr + off));
-                }
-                let checksum = (0u8).wrapping_sub(sum);
-                bus.write_u8(table_addr + 0x41, checksum);
+                },
+            };
+            if !edd::write_drive_params(bus, table_addr, buf_size, &params) {
+                set_error(bios, cpu, 0x01);
+                return;
             }
 
             bios.last_int13_status = 0;
             cpu.rflags &= !FLAG_CF;
             cpu.gpr[gpr::RAX] &= !0xFF00u64;
         }
+        0x4E => {
+            // Set hardware configuration (EDD).
+            //
+            // AL selects prefetch / PIO / DMA behaviour for INT 13h transfers. BIOS disk I/O
+            // bypasses the emulated controllers, so every defined subfunction is accepted as a
+            // no-op; AL=0 on return reports that no other drives were affected.
+            if !drive_present(bios, bus, drive, cdrom_present) {
+                set_error(bios, cpu, 0x01);
+                return;
+            }
+            if drive < 0x80 {
+                set_error(bios, cpu, 0x01);
+                return;
+            }
+            let al = (cpu.gpr[gpr::RAX] & 0xFF) as u8;
+            if !(0x01..=0x07).contains(&al) {
+                set_error(bios, cpu, 0x01);
+                return;
+            }
+
+            bios.last_int13_status = 0;
+            cpu.rflags &= !FLAG_CF;
+            cpu.gpr[gpr::RAX] &= !0xFFFFu64;
+        }
         _ => {
             const LOG_LIMIT: u32 = 16;
             let count = bios.unhandled_interrupt_log_count;
@@ -1646,13 +1680,7 @@ fn handle_int15(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
         }
         0xE801 => {
             // Alternative extended memory query used by many bootloaders.
-            if bios.e820_map.is_empty() {
-                bios.e820_map = build_e820_map(
-                    bios.config.memory_size_bytes,
-                    bios.acpi_reclaimable,
-                    bios.acpi_nvs,
-                );
-            }
+            ensure_e820_map(bios);
 
             let (ax_kb, bx_blocks) = e801_from_e820(&bios.e820_map);
             cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | (ax_kb as u64);
@@ -1674,13 +1702,7 @@ fn handle_int15(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
                 return;
             }
 
-            if bios.e820_map.is_empty() {
-                bios.e820_map = build_e820_map(
-                    bios.config.memory_size_bytes,
-                    bios.acpi_reclaimable,
-                    bios.acpi_nvs,
-                );
-            }
+            ensure_e820_map(bios);
 
             let idx = (cpu.gpr[gpr::RBX] & 0xFFFF_FFFF) as usize;
             if idx >= bios.e820_map.len() {
@@ -1748,9 +1770,10 @@ fn handle_int15(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
                 cpu.rflags &= !FLAG_CF;
             }
             0x88 => {
-                // Extended memory size (KB above 1MB).
-                let ext_kb = bios.config.memory_size_bytes.saturating_sub(1024 * 1024) / 1024;
-                cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | ext_kb.min(0xFFFF);
+                // Extended memory size (KB of contiguous memory above 1MB).
+                ensure_e820_map(bios);
+                let ext_kb = extended_kb_from_e820(&bios.e820_map);
+                cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | u64::from(ext_kb);
                 cpu.rflags &= !FLAG_CF;
             }
             _ => {
@@ -1771,6 +1794,30 @@ fn handle_int15(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
     }
 }
 
+/// Build the E820 map on first use. E801 and AH=88h read the same cached map, so all three INT 15h
+/// sizing interfaces agree.
+fn ensure_e820_map(bios: &mut Bios) {
+    if bios.e820_map.is_empty() {
+        bios.e820_map = super::memory_map::e820_from_memory_map(&bios.guest_memory_map());
+    }
+}
+
+/// INT 15h AH=88h: KB of memory contiguous from 1MiB, capped at 63MiB (0xFC00 KB) like real BIOSes
+/// so the 16-bit result never wraps.
+fn extended_kb_from_e820(map: &[E820Entry]) -> u16 {
+    const ONE_MIB: u64 = 0x0010_0000;
+
+    let mut end = ONE_MIB;
+    while let Some(entry) = map.iter().find(|entry| {
+        matches!(entry.region_type, E820_RAM | E820_ACPI | E820_NVS)
+            && entry.base <= end
+            && entry.base.saturating_add(entry.length) > end
+    }) {
+        end = entry.base.saturating_add(entry.length);
+    }
+    ((end - ONE_MIB) / 1024).min(0xFC00) as u16
+}
+
 fn e801_from_e820(map: &[E820Entry]) -> (u16, u16) {
     const ONE_MIB: u64 = 0x0010_0000;
     const SIXTEEN_MIB: u64 = 0x0100_0000;
@@ -2022,6 +2069,10 @@ fn handle_int1a(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
                 }
             }
         }
+        0x56 => {
+            // PXE installation check and API entry points (see `pxe.rs`).
+            super::pxe::handle_int1a_pxe(cpu, bus);
+        }
         _ => {
             // Common extension probe: PCI BIOS interface uses AH=0xB1.
             //
@@ -2037,143 +2088,6 @@ fn handle_int1a(bios: &mut Bios, cpu: &mut CpuState, bus: &mut dyn BiosBus) {
     }
 }
 
-fn build_e820_map(
-    total_memory: u64,
-    acpi_region: Option<(u64, u64)>,
-    nvs_region: Option<(u64, u64)>,
-) -> Vec<E820Entry> {
-    let mut map = Vec::new();
-    const ONE_MIB: u64 = 0x0010_0000;
-    // PCIe ECAM / MMCONFIG window reserved for PCI configuration space accesses.
-    //
-    // These constants are re-exported from `bios` (source-of-truth: `aero-pc-constants`) so the
-    // ECAM window stays consistent across:
-    // - ACPI `MCFG` generation
-    // - platform MMIO mapping
-    // - E820 reservations
-    const PCIE_ECAM_BASE: u64 = super::PCIE_ECAM_BASE;
-    const PCIE_ECAM_SIZE: u64 = super::PCIE_ECAM_SIZE;
-    // The start of the "low memory" window available for RAM below 4GiB.
-    //
-    // The ECAM region lives immediately below the typical PCI BAR allocation window, so any RAM
-    // beyond this address must be remapped above 4GiB to keep the ECAM window reserved.
-    const LOW_RAM_END: u64 = PCIE_ECAM_BASE;
-    // Typical x86 systems reserve a PCI/MMIO window below 4GiB. This must be
-    // reported via E820 so OSes (notably Windows) do not treat device MMIO as RAM.
-    const PCI_HOLE_START: u64 = 0xC000_0000;
-    const PCI_HOLE_END: u64 = 0x1_0000_0000;
-
-    fn push_region(entries: &mut Vec<E820Entry>, base: u64, end: u64, region_type: u32) {
-        if end <= base {
-            return;
-        }
-        entries.push(E820Entry {
-            base,
-            length: end - base,
-            region_type,
-            extended_attributes: 1,
-        });
-    }
-
-    fn push_ram_split_by_reserved(
-        entries: &mut Vec<E820Entry>,
-        base: u64,
-        end: u64,
-        reserved: &[(u64, u64, u32)],
-    ) {
-        if end <= base {
-            return;
-        }
-
-        let mut cursor = base;
-        for &(r_base, r_len, r_type) in reserved {
-            let r_end = r_base.saturating_add(r_len);
-            let mut a_start = r_base.clamp(base, end);
-            let a_end = r_end.clamp(base, end);
-            if a_end <= a_start {
-                continue;
-            }
-
-            // The reserved windows are expected to be sorted by base, but may still overlap if a
-            // caller provides inconsistent ACPI/NVS placements. Clamp to `cursor` so we never emit
-            // overlapping E820 entries.
-            if a_end <= cursor {
-                continue;
-            }
-            if a_start < cursor {
-                a_start = cursor;
-            }
-
-            if a_start > cursor {
-                push_region(entries, cursor, a_start, E820_RAM);
-            }
-            push_region(entries, a_start, a_end, r_type);
-            cursor = a_end;
-        }
-
-        if end > cursor {
-            push_region(entries, cursor, end, E820_RAM);
-        }
-    }
-
-    let mut reserved = Vec::new();
-    if let Some((base, len)) = acpi_region {
-        reserved.push((base, len, E820_ACPI));
-    }
-    if let Some((base, len)) = nvs_region {
-        reserved.push((base, len, E820_NVS));
-    }
-    reserved.sort_by_key(|(base, _, _)| *base);
-
-    // Conventional memory (0 - EBDA).
-    //
-    // Clamp the usable RAM entry to the configured guest RAM size so we never report more RAM
-    // than actually exists (e.g. for pathological/defensive configurations like `total_memory=0`).
-    push_region(&mut map, 0, EBDA_BASE.min(total_memory), E820_RAM);
-
-    // EBDA reserved.
-    push_region(
-        &mut map,
-        EBDA_BASE,
-        EBDA_BASE + EBDA_SIZE as u64,
-        E820_RESERVED,
-    );
-
-    // VGA/BIOS/option ROM region.
-    push_region(&mut map, 0x000A_0000, ONE_MIB, E820_RESERVED);
-
-    if total_memory <= ONE_MIB {
-        // Guest RAM smaller than 1MiB is unusual, but the map is still well-formed:
-        // - Conventional RAM is clamped above.
-        // - EBDA/VGA regions remain reserved.
-        return map;
-    }
-
-    // Low extended memory: [1MiB, LOW_RAM_END) with reserved splits.
-    let low_ram_end = total_memory.min(LOW_RAM_END);
-    push_ram_split_by_reserved(&mut map, ONE_MIB, low_ram_end, &reserved);
-
-    // PCI/MMIO hole + high memory remap when total RAM exceeds the low RAM window.
-    if total_memory > LOW_RAM_END {
-        // Reserve the PCIe ECAM window (MCFG / config space).
-        push_region(
-            &mut map,
-            PCIE_ECAM_BASE,
-            PCIE_ECAM_BASE.saturating_add(PCIE_ECAM_SIZE),
-            E820_RESERVED,
-        );
-
-        // Reserve the remaining PCI/MMIO window below 4GiB.
-        push_region(&mut map, PCI_HOLE_START, PCI_HOLE_END, E820_RESERVED);
-
-        let high_ram_len = total_memory - LOW_RAM_END;
-        let high_ram_end = PCI_HOLE_END.saturating_add(high_ram_len);
-        push_ram_split_by_reserved(&mut map, PCI_HOLE_END, high_ram_end, &reserved);
-    }
-
-    map
-}
-
 #[cfg(test)]
 mod tests {
     use super::super::{
@@ -3161,7 +3075,8 @@ mod tests {
             assert_eq!(cpu.rflags & FLAG_CF, 0);
             assert_eq!(mem.read_u16(table_addr), 0x42);
             assert_eq!(mem.read_u16(table_addr + 0x1E), 0xBEDD);
-            assert_eq!(mem.read_u8(table_addr + 0x20), 0x1E);
+            // Device path information length: key (0x1E) through checksum (0x41).
+            assert_eq!(mem.read_u8(table_addr + 0x20), 0x24);
 
             assert_eq!(mem.read_bytes(table_addr + 0x24, 4), b"PCI ".to_vec());
 
@@ -3173,9 +3088,9 @@ mod tests {
             assert_eq!(mem.read_bytes(table_addr + 0x28, 8), expected_iface);
             assert_eq!(mem.read_bytes(table_addr + 0x30, 16), vec![0u8; 16]);
 
-            // Verify checksum: sum(host_bus..checksum) must be 0 mod 256.
+            // Verify checksum: sum(key..checksum) must be 0 mod 256.
             let mut sum: u8 = 0;
-            for b in mem.read_bytes(table_addr + 0x24, 0x1E) {
+            for b in mem.read_bytes(table_addr + 0x1E, 0x24) {
                 sum = sum.wrapping_add(b);
             }
             assert_eq!(sum, 0);
@@ -3229,6 +3144,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn int13_ext_seek_and_set_hardware_config_validate_inputs() {
+        let mut bios = Bios::new(BiosConfig::default());
+        let mut disk = InMemoryDisk::new(vec![0u8; BIOS_SECTOR_SIZE * 8]);
+        let mut mem = TestMemory::new(2 * 1024 * 1024);
+        ivt::init_bda(&mut mem, 0x80);
+
+        let mut cpu = CpuState::new(CpuMode::Real);
+        cpu.a20_enabled = mem.a20_enabled();
+        set_real_mode_seg(&mut cpu.segments.ds, 0);
+        cpu.gpr[gpr::RSI] = 0x0500;
+        let dap_addr = cpu.apply_a20(cpu.segments.ds.base + 0x0500);
+        mem.write_u8(dap_addr, 0x10);
+        for (lba, status) in [(7u64, 0u64), (8, 0x04)] {
+            mem.write_u64(dap_addr + 8, lba);
+            cpu.gpr[gpr::RAX] = 0x4700; // AH=47h extended seek
+            cpu.gpr[gpr::RDX] = 0x80;
+            handle_int13(&mut bios, &mut cpu, &mut mem, &mut disk, None);
+            assert_eq!((cpu.gpr[gpr::RAX] >> 8) & 0xFF, status, "lba={lba}");
+            assert_eq!(cpu.rflags & FLAG_CF != 0, status != 0);
+        }
+
+        for (al, status) in [(0x01u64, 0u64), (0x07, 0), (0x08, 0x01)] {
+            cpu.gpr[gpr::RAX] = 0x4E00 | al; // AH=4Eh set hardware configuration
+            cpu.gpr[gpr::RDX] = 0x80;
+            handle_int13(&mut bios, &mut cpu, &mut mem, &mut disk, None);
+            assert_eq!((cpu.gpr[gpr::RAX] >> 8) & 0xFF, status, "al={al:#x}");
+            if status == 0 {
+                // AL=0: no other drives affected.
+                assert_eq!(cpu.gpr[gpr::RAX] & 0xFF, 0);
+            }
+        }
+    }
+
     #[test]
     fn int13_chs_read_floppy_maps_head1_sector1_to_lba18() {
         // 1.44MiB floppy = 2880 sectors. Cylinder 0, head 1, sector 1 corresponds to LBA 18.
@@ -4272,6 +4221,18 @@ mod tests {
         assert_ne!(cpu.rflags & FLAG_ZF, 0);
     }
 
+    fn build_e820_map(
+        total_memory: u64,
+        acpi_region: Option<(u64, u64)>,
+        nvs_region: Option<(u64, u64)>,
+    ) -> Vec<E820Entry> {
+        super::super::memory_map::e820_from_memory_map(&super::super::guest_memory_map(
+            total_memory,
+            acpi_region,
+            nvs_region,
+        ))
+    }
+
     #[test]
     fn e820_reserves_pcie_ecam_window() {
         const FOUR_GIB: u64 = 4 * 1024 * 1024 * 1024;
@@ -4358,10 +4319,26 @@ mod tests {
         let map = build_e820_map(TOTAL_MEMORY, None, None);
         assert_e820_sorted_and_non_overlapping(&map);
 
-        // When RAM ends below the PCIe ECAM window, the E820 map should not mention the ECAM or
-        // PCI/MMIO hole ranges at all.
-        assert_no_e820_entry_overlaps_range(&map, ECAM_BASE, ECAM_BASE + ECAM_SIZE);
-        assert_no_e820_entry_overlaps_range(&map, PCI_HOLE_START, PCI_HOLE_END);
+        // When RAM ends below the PCIe ECAM window there is no PCI hole entry, but the ECAM window
+        // and the chipset MMIO pages are still reserved explicitly.
+        assert!(map.iter().any(|e| {
+            e.base == ECAM_BASE && e.length == ECAM_SIZE && e.region_type == E820_RESERVED
+        }));
+        for base in [0xFEC0_0000, 0xFED0_0000, 0xFEE0_0000] {
+            assert!(
+                map.iter().any(|e| {
+                    e.base == base && e.length == 0x1000 && e.region_type == E820_RESERVED
+                }),
+                "missing reserved page at {base:#x}: {map:?}"
+            );
+        }
+        let ram: Vec<_> = map
+            .iter()
+            .copied()
+            .filter(|e| e.region_type == E820_RAM)
+            .collect();
+        assert_no_e820_entry_overlaps_range(&ram, ECAM_BASE, ECAM_BASE + ECAM_SIZE);
+        assert_no_e820_entry_overlaps_range(&ram, PCI_HOLE_START, PCI_HOLE_END);
 
         // Ensure the amount of RAM described by E820 matches the configured guest memory, minus
         // the legacy VGA + EBDA reserved region within the first MiB.
@@ -4846,6 +4823,27 @@ mod tests {
         const ONE_MIB: u64 = 0x0010_0000;
         const FOUR_GIB: u64 = 0x1_0000_0000;
         const VGA_START: u64 = 0x000A_0000;
+        const ROM_SHADOW_START: u64 = 0x000C_0000;
+
+        // Without a PCI hole entry the ECAM window and the chipset MMIO pages (IOAPIC, HPET,
+        // LAPIC) are reserved explicitly after RAM.
+        let no_hole_reserved = || {
+            let mut entries = vec![E820Entry {
+                base: PCIE_ECAM_BASE,
+                length: PCIE_ECAM_SIZE,
+                region_type: E820_RESERVED,
+                extended_attributes: 1,
+            }];
+            entries.extend(
+                [0xFEC0_0000, 0xFED0_0000, 0xFEE0_0000].map(|base| E820Entry {
+                    base,
+                    length: 0x1000,
+                    region_type: E820_RESERVED,
+                    extended_attributes: 1,
+                }),
+            );
+            entries
+        };
 
         let cases = [
             // RAM < 640KiB edge case: ensure we clamp the conventional RAM entry so we never
@@ -4853,146 +4851,196 @@ mod tests {
             Case {
                 name: "512KiB",
                 total_memory: 512 * 1024,
-                expected: vec![
-                    E820Entry {
-                        base: 0x0000_0000,
-                        length: 512 * 1024,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: EBDA_BASE,
-                        length: EBDA_SIZE as u64,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: VGA_START,
-                        length: ONE_MIB - VGA_START,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                ],
+                expected: [
+                    vec![
+                        E820Entry {
+                            base: 0x0000_0000,
+                            length: 512 * 1024,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: EBDA_BASE,
+                            length: EBDA_SIZE as u64,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: VGA_START,
+                            length: ROM_SHADOW_START - VGA_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ROM_SHADOW_START,
+                            length: ONE_MIB - ROM_SHADOW_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                    ],
+                    no_hole_reserved(),
+                ]
+                .concat(),
             },
             Case {
                 name: "16MiB",
                 total_memory: 16 * 1024 * 1024,
-                expected: vec![
-                    E820Entry {
-                        base: 0x0000_0000,
-                        length: EBDA_BASE,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: EBDA_BASE,
-                        length: EBDA_SIZE as u64,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: VGA_START,
-                        length: ONE_MIB - VGA_START,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: ONE_MIB,
-                        length: 16 * 1024 * 1024 - ONE_MIB,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                ],
+                expected: [
+                    vec![
+                        E820Entry {
+                            base: 0x0000_0000,
+                            length: EBDA_BASE,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: EBDA_BASE,
+                            length: EBDA_SIZE as u64,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: VGA_START,
+                            length: ROM_SHADOW_START - VGA_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ROM_SHADOW_START,
+                            length: ONE_MIB - ROM_SHADOW_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ONE_MIB,
+                            length: 16 * 1024 * 1024 - ONE_MIB,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                    ],
+                    no_hole_reserved(),
+                ]
+                .concat(),
             },
             Case {
                 name: "32MiB",
                 total_memory: 32 * 1024 * 1024,
-                expected: vec![
-                    E820Entry {
-                        base: 0x0000_0000,
-                        length: EBDA_BASE,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: EBDA_BASE,
-                        length: EBDA_SIZE as u64,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: VGA_START,
-                        length: ONE_MIB - VGA_START,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: ONE_MIB,
-                        length: 32 * 1024 * 1024 - ONE_MIB,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                ],
+                expected: [
+                    vec![
+                        E820Entry {
+                            base: 0x0000_0000,
+                            length: EBDA_BASE,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: EBDA_BASE,
+                            length: EBDA_SIZE as u64,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: VGA_START,
+                            length: ROM_SHADOW_START - VGA_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ROM_SHADOW_START,
+                            length: ONE_MIB - ROM_SHADOW_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ONE_MIB,
+                            length: 32 * 1024 * 1024 - ONE_MIB,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                    ],
+                    no_hole_reserved(),
+                ]
+                .concat(),
             },
             Case {
                 name: "just below ECAM base",
                 total_memory: PCIE_ECAM_BASE - 1,
-                expected: vec![
-                    E820Entry {
-                        base: 0x0000_0000,
-                        length: EBDA_BASE,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: EBDA_BASE,
-                        length: EBDA_SIZE as u64,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: VGA_START,
-                        length: ONE_MIB - VGA_START,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: ONE_MIB,
-                        length: (PCIE_ECAM_BASE - 1) - ONE_MIB,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                ],
+                expected: [
+                    vec![
+                        E820Entry {
+                            base: 0x0000_0000,
+                            length: EBDA_BASE,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: EBDA_BASE,
+                            length: EBDA_SIZE as u64,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: VGA_START,
+                            length: ROM_SHADOW_START - VGA_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ROM_SHADOW_START,
+                            length: ONE_MIB - ROM_SHADOW_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ONE_MIB,
+                            length: (PCIE_ECAM_BASE - 1) - ONE_MIB,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                    ],
+                    no_hole_reserved(),
+                ]
+                .concat(),
             },
             Case {
                 name: "exactly ECAM base",
                 total_memory: PCIE_ECAM_BASE,
-                expected: vec![
-                    E820Entry {
-                        base: 0x0000_0000,
-                        length: EBDA_BASE,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: EBDA_BASE,
-                        length: EBDA_SIZE as u64,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: VGA_START,
-                        length: ONE_MIB - VGA_START,
-                        region_type: E820_RESERVED,
-                        extended_attributes: 1,
-                    },
-                    E820Entry {
-                        base: ONE_MIB,
-                        length: PCIE_ECAM_BASE - ONE_MIB,
-                        region_type: E820_RAM,
-                        extended_attributes: 1,
-                    },
-                ],
+                expected: [
+                    vec![
+                        E820Entry {
+                            base: 0x0000_0000,
+                            length: EBDA_BASE,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: EBDA_BASE,
+                            length: EBDA_SIZE as u64,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: VGA_START,
+                            length: ROM_SHADOW_START - VGA_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ROM_SHADOW_START,
+                            length: ONE_MIB - ROM_SHADOW_START,
+                            region_type: E820_RESERVED,
+                            extended_attributes: 1,
+                        },
+                        E820Entry {
+                            base: ONE_MIB,
+                            length: PCIE_ECAM_BASE - ONE_MIB,
+                            region_type: E820_RAM,
+                            extended_attributes: 1,
+                        },
+                    ],
+                    no_hole_reserved(),
+                ]
+                .concat(),
             },
             Case {
                 name: "just above ECAM base",
@@ -5012,7 +5060,13 @@ mod tests {
                     },
                     E820Entry {
                         base: VGA_START,
-                        length: ONE_MIB - VGA_START,
+                        length: ROM_SHADOW_START - VGA_START,
+                        region_type: E820_RESERVED,
+                        extended_attributes: 1,
+                    },
+                    E820Entry {
+                        base: ROM_SHADOW_START,
+                        length: ONE_MIB - ROM_SHADOW_START,
                         region_type: E820_RESERVED,
                         extended_attributes: 1,
                     },
@@ -5060,7 +5114,13 @@ mod tests {
                     },
                     E820Entry {
                         base: VGA_START,
-                        length: ONE_MIB - VGA_START,
+                        length: ROM_SHADOW_START - VGA_START,
+                        region_type: E820_RESERVED,
+                        extended_attributes: 1,
+                    },
+                    E820Entry {
+                        base: ROM_SHADOW_START,
+                        length: ONE_MIB - ROM_SHADOW_START,
                         region_type: E820_RESERVED,
                         extended_attributes: 1,
                     },
@@ -5108,7 +5168,13 @@ mod tests {
                     },
                     E820Entry {
                         base: VGA_START,
-                        length: ONE_MIB - VGA_START,
+                        length: ROM_SHADOW_START - VGA_START,
+                        region_type: E820_RESERVED,
+                        extended_attributes: 1,
+                    },
+                    E820Entry {
+                        base: ROM_SHADOW_START,
+                        length: ONE_MIB - ROM_SHADOW_START,
                         region_type: E820_RESERVED,
                         extended_attributes: 1,
                     },
@@ -140,6 +140,23 @@ pub fn init_bda(bus: &mut dyn BiosBus, boot_drive: u8) {
     bus.write_u16(EBDA_BASE, (EBDA_SIZE / 1024) as u16);
 }
 
+/// Publish the platform's serial ports in the BDA COM port table and the INT 11h equipment word.
+///
+/// `ports` lists the I/O bases of the present UARTs in COM1..COM4 order; at most four are used.
+/// [`init_bda`] advertises COM1 only, so POST calls this afterwards with the configured ports.
+pub fn init_bda_serial_ports(bus: &mut dyn BiosBus, ports: &[u16]) {
+    let count = ports.len().min(4);
+    for slot in 0..4u64 {
+        let base = ports.get(slot as usize).copied().unwrap_or(0);
+        bus.write_u16(BDA_BASE + BDA_COM_PORTS_OFFSET + slot * 2, base);
+    }
+
+    // Equipment word bits 9-11: number of serial ports.
+    let equipment = bus.read_u16(BDA_BASE + BDA_EQUIPMENT_WORD_OFFSET);
+    let equipment = (equipment & !(0x7 << 9)) | ((count as u16) << 9);
+    bus.write_u16(BDA_BASE + BDA_EQUIPMENT_WORD_OFFSET, equipment);
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::{BDA_KEYBOARD_BUF_END, BDA_KEYBOARD_BUF_START};
@@ -184,6 +201,23 @@ mod tests {
         assert_eq!(mem.read_u8(BDA_BASE + BDA_HARD_DISK_COUNT_OFFSET), 1);
     }
 
+    #[test]
+    fn init_bda_serial_ports_populates_com_table_and_equipment_word() {
+        let mut mem = TestMemory::new(2 * 1024 * 1024);
+        init_bda(&mut mem, 0x80);
+        init_bda_serial_ports(&mut mem, &[0x3F8, 0x2F8, 0x2E8]);
+
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_COM_PORTS_OFFSET), 0x03F8);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_COM_PORTS_OFFSET + 2), 0x02F8);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_COM_PORTS_OFFSET + 4), 0x02E8);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_COM_PORTS_OFFSET + 6), 0);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_EQUIPMENT_WORD_OFFSET), 0x0622);
+
+        init_bda_serial_ports(&mut mem, &[]);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_COM_PORTS_OFFSET), 0);
+        assert_eq!(mem.read_u16(BDA_BASE + BDA_EQUIPMENT_WORD_OFFSET), 0x0022);
+    }
+
     #[test]
     fn init_bda_advertises_floppy_in_equipment_word_when_booting_from_floppy() {
         let mut mem = TestMemory::new(2 * 1024 * 1024);
@@ -0,0 +1,537 @@
+//! Guest-physical memory layout presented by the BIOS.
+//!
+//! [`guest_memory_map`] is the single source of truth for the layout: the INT 15h E820 (and E801)
+//! answers are derived from it via [`e820_from_memory_map`], and hosts can query the finer-grained
+//! view (PCI hole, remapped high RAM, ROM shadow, ...) through [`Bios::guest_memory_map`], so the
+//! two can never diverge.
+//!
+//! The layout matches the RAM remap performed by `aero_platform::memory::MemoryBus::with_ram`: RAM
+//! beyond [`PCIE_ECAM_BASE`] is exposed starting at 4GiB.
+//!
+//! The ECAM window and the chipset/device MMIO decoders (IOAPIC, HPET, LAPIC, TPM CRB, a
+//! device-backed VBE LFB) are always reported as reserved: either inside the PCI hole, or, when RAM
+//! is too small for the map to have one, as explicit [`MemoryRegionKind::Mmio`] entries.
+
+use super::interrupts::{E820_ACPI, E820_NVS, E820_RAM, E820_RESERVED};
+use super::{Bios, E820Entry, EBDA_BASE, EBDA_SIZE, PCIE_ECAM_BASE, PCIE_ECAM_SIZE};
+use crate::acpi::{HPET_BASE, IO_APIC_BASE, LOCAL_APIC_BASE};
+
+const ONE_MIB: u64 = 0x0010_0000;
+/// Legacy VGA memory window.
+const LEGACY_VGA_BASE: u64 = 0x000A_0000;
+/// Option ROM + system BIOS shadow area.
+const ROM_SHADOW_BASE: u64 = 0x000C_0000;
+/// The start of the PCI/MMIO window below 4GiB (see `aero_pc_constants::PCI_MMIO_BASE`).
+const PCI_HOLE_START: u64 = 0xC000_0000;
+/// End of the 32-bit physical address space; remapped RAM starts here.
+const HIGH_RAM_BASE: u64 = 0x1_0000_0000;
+/// The end of the "low memory" window available for RAM below 4GiB.
+///
+/// The ECAM region lives immediately below the PCI BAR allocation window, so any RAM beyond this
+/// address must be remapped above 4GiB to keep the ECAM window reserved.
+const LOW_RAM_END: u64 = PCIE_ECAM_BASE;
+/// Granularity of the MMIO windows reported in the map.
+const MMIO_PAGE_SIZE: u64 = 0x1000;
+/// Size of the TPM CRB register window (matches the `_CRS` published in the ACPI DSDT).
+const TPM_CRB_WINDOW_SIZE: u64 = 0x5000;
+/// Chipset MMIO pages present on every platform, as `(base, len)`.
+const CHIPSET_MMIO_WINDOWS: [(u64, u64); 3] = [
+    (IO_APIC_BASE as u64, MMIO_PAGE_SIZE),
+    (HPET_BASE, MMIO_PAGE_SIZE),
+    (LOCAL_APIC_BASE as u64, MMIO_PAGE_SIZE),
+];
+
+/// What occupies a [`MemoryRegion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryRegionKind {
+    /// Guest RAM at its natural address (below the PCI hole).
+    UsableRam,
+    /// Firmware-reserved RAM (EBDA).
+    Reserved,
+    /// RAM holding ACPI tables that the OS may reclaim after parsing them.
+    AcpiReclaim,
+    /// ACPI non-volatile storage.
+    AcpiNvs,
+    /// PCI/MMIO window below 4GiB (BARs, IOAPIC/LAPIC/HPET, BIOS alias).
+    PciHole,
+    /// Guest RAM remapped above 4GiB to make room for the ECAM window and PCI hole.
+    HighRamRemap,
+    /// Option ROM and system BIOS shadow area below 1MiB.
+    RomShadow,
+    /// Fixed MMIO decoders outside the PCI hole (legacy VGA window, PCIe ECAM, and the chipset and
+    /// device windows when the map has no PCI hole).
+    Mmio,
+}
+
+impl MemoryRegionKind {
+    /// The E820 type the BIOS reports for this kind of region.
+    pub fn e820_type(self) -> u32 {
+        match self {
+            Self::UsableRam | Self::HighRamRemap => E820_RAM,
+            Self::AcpiReclaim => E820_ACPI,
+            Self::AcpiNvs => E820_NVS,
+            Self::Reserved | Self::PciHole | Self::RomShadow | Self::Mmio => E820_RESERVED,
+        }
+    }
+
+    /// Whether the region is backed by guest RAM.
+    pub fn is_ram(self) -> bool {
+        matches!(
+            self,
+            Self::UsableRam | Self::HighRamRemap | Self::AcpiReclaim | Self::AcpiNvs
+        )
+    }
+}
+
+/// A guest-physical address range `start..start + len`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub len: u64,
+    pub kind: MemoryRegionKind,
+}
+
+impl MemoryRegion {
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.len)
+    }
+}
+
+/// Build the guest-physical memory map for `total_memory` bytes of guest RAM.
+///
+/// `acpi_region` / `nvs_region` are the `(base, len)` windows holding the ACPI tables (as recorded
+/// during POST). Regions are sorted by address and never overlap, even if the ACPI windows
+/// overlap each other.
+pub fn guest_memory_map(
+    total_memory: u64,
+    acpi_region: Option<(u64, u64)>,
+    nvs_region: Option<(u64, u64)>,
+) -> Vec<MemoryRegion> {
+    guest_memory_map_with_mmio(total_memory, acpi_region, nvs_region, &[])
+}
+
+/// [`guest_memory_map`] with additional platform MMIO windows (`(base, len)`, e.g. a TPM CRB or a
+/// device-backed VBE LFB) to keep out of RAM.
+///
+/// Windows are rounded out to 4KiB pages. Only the part between the PCI MMIO base and 4GiB is
+/// considered: below it guest RAM (or the ECAM window) is authoritative.
+pub fn guest_memory_map_with_mmio(
+    total_memory: u64,
+    acpi_region: Option<(u64, u64)>,
+    nvs_region: Option<(u64, u64)>,
+    mmio_windows: &[(u64, u64)],
+) -> Vec<MemoryRegion> {
+    fn push_region(map: &mut Vec<MemoryRegion>, start: u64, end: u64, kind: MemoryRegionKind) {
+        if end <= start {
+            return;
+        }
+        map.push(MemoryRegion {
+            start,
+            len: end - start,
+            kind,
+        });
+    }
+
+    fn push_ram_split_by_reserved(
+        map: &mut Vec<MemoryRegion>,
+        base: u64,
+        end: u64,
+        ram_kind: MemoryRegionKind,
+        reserved: &[(u64, u64, MemoryRegionKind)],
+    ) {
+        if end <= base {
+            return;
+        }
+
+        let mut cursor = base;
+        for &(r_base, r_len, r_kind) in reserved {
+            let r_end = r_base.saturating_add(r_len);
+            let mut a_start = r_base.clamp(base, end);
+            let a_end = r_end.clamp(base, end);
+            if a_end <= a_start {
+                continue;
+            }
+
+            // The reserved windows are expected to be sorted by base, but may still overlap if a
+            // caller provides inconsistent ACPI/NVS placements. Clamp to `cursor` so we never emit
+            // overlapping regions.
+            if a_end <= cursor {
+                continue;
+            }
+            if a_start < cursor {
+                a_start = cursor;
+            }
+
+            if a_start > cursor {
+                push_region(map, cursor, a_start, ram_kind);
+            }
+            push_region(map, a_start, a_end, r_kind);
+            cursor = a_end;
+        }
+
+        if end > cursor {
+            push_region(map, cursor, end, ram_kind);
+        }
+    }
+
+    let mut reserved = Vec::new();
+    if let Some((base, len)) = acpi_region {
+        reserved.push((base, len, MemoryRegionKind::AcpiReclaim));
+    }
+    if let Some((base, len)) = nvs_region {
+        reserved.push((base, len, MemoryRegionKind::AcpiNvs));
+    }
+    reserved.sort_by_key(|(base, _, _)| *base);
+
+    let mut map = Vec::new();
+
+    // Conventional memory (0 - EBDA).
+    //
+    // Clamp the usable RAM region to the configured guest RAM size so we never report more RAM
+    // than actually exists (e.g. for pathological/defensive configurations like `total_memory=0`).
+    push_region(
+        &mut map,
+        0,
+        EBDA_BASE.min(total_memory),
+        MemoryRegionKind::UsableRam,
+    );
+    push_region(
+        &mut map,
+        EBDA_BASE,
+        EBDA_BASE + EBDA_SIZE as u64,
+        MemoryRegionKind::Reserved,
+    );
+    push_region(
+        &mut map,
+        LEGACY_VGA_BASE,
+        ROM_SHADOW_BASE,
+        MemoryRegionKind::Mmio,
+    );
+    push_region(
+        &mut map,
+        ROM_SHADOW_BASE,
+        ONE_MIB,
+        MemoryRegionKind::RomShadow,
+    );
+
+    // Low extended memory: [1MiB, LOW_RAM_END) with ACPI splits. Guest RAM smaller than 1MiB is
+    // unusual, but the map is still well-formed: conventional RAM is clamped above and the
+    // EBDA/VGA/ROM and MMIO regions remain reserved.
+    let low_ram_end = total_memory.min(LOW_RAM_END);
+    push_ram_split_by_reserved(
+        &mut map,
+        ONE_MIB,
+        low_ram_end,
+        MemoryRegionKind::UsableRam,
+        &reserved,
+    );
+
+    // PCI/MMIO hole + high memory remap when total RAM exceeds the low RAM window.
+    if total_memory > LOW_RAM_END {
+        push_region(
+            &mut map,
+            PCIE_ECAM_BASE,
+            PCIE_ECAM_BASE.saturating_add(PCIE_ECAM_SIZE),
+            MemoryRegionKind::Mmio,
+        );
+        push_region(
+            &mut map,
+            PCI_HOLE_START,
+            HIGH_RAM_BASE,
+            MemoryRegionKind::PciHole,
+        );
+
+        let high_ram_len = total_memory - LOW_RAM_END;
+        let high_ram_end = HIGH_RAM_BASE.saturating_add(high_ram_len);
+        push_ram_split_by_reserved(
+            &mut map,
+            HIGH_RAM_BASE,
+            high_ram_end,
+            MemoryRegionKind::HighRamRemap,
+            &reserved,
+        );
+    } else {
+        // Without a PCI hole entry nothing above RAM would be described at all; reserve the
+        // decoders explicitly so a guest never probes them as memory.
+        push_region(
+            &mut map,
+            PCIE_ECAM_BASE,
+            PCIE_ECAM_BASE.saturating_add(PCIE_ECAM_SIZE),
+            MemoryRegionKind::Mmio,
+        );
+        for (start, end) in merged_mmio_windows(mmio_windows) {
+            push_region(&mut map, start, end, MemoryRegionKind::Mmio);
+        }
+    }
+
+    map
+}
+
+/// The chipset windows plus `extra`, clamped to `[PCI_HOLE_START, HIGH_RAM_BASE)`, page-aligned,
+/// sorted and merged so the result never overlaps.
+fn merged_mmio_windows(extra: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut windows: Vec<(u64, u64)> = CHIPSET_MMIO_WINDOWS
+        .iter()
+        .chain(extra)
+        .filter_map(|&(base, len)| {
+            let start = (base & !(MMIO_PAGE_SIZE - 1)).max(PCI_HOLE_START);
+            let end = base
+                .saturating_add(len)
+                .min(HIGH_RAM_BASE)
+                .next_multiple_of(MMIO_PAGE_SIZE);
+            (end > start).then_some((start, end))
+        })
+        .collect();
+    windows.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(windows.len());
+    for (start, end) in windows {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Derive the E820 map reported by INT 15h from a [`guest_memory_map`] layout.
+pub fn e820_from_memory_map(map: &[MemoryRegion]) -> Vec<E820Entry> {
+    map.iter()
+        .map(|region| E820Entry {
+            base: region.start,
+            length: region.len,
+            region_type: region.kind.e820_type(),
+            extended_attributes: 1,
+        })
+        .collect()
+}
+
+/// Guest-physical `(base, len)` ranges backing the RAM offsets `[offset, offset + len)`.
+///
+/// RAM offsets beyond the low RAM window are remapped above 4GiB, matching the layout of
+/// [`guest_memory_map`]; a range straddling the window boundary is split in two.
+pub fn ram_physical_ranges(offset: u64, len: u64) -> Vec<(u64, u64)> {
+    let end = offset.saturating_add(len);
+    let mut ranges = Vec::with_capacity(2);
+    if offset < LOW_RAM_END && end > offset {
+        ranges.push((offset, end.min(LOW_RAM_END) - offset));
+    }
+    if end > LOW_RAM_END {
+        let high_start = offset.max(LOW_RAM_END);
+        ranges.push((HIGH_RAM_BASE + (high_start - LOW_RAM_END), end - high_start));
+    }
+    ranges
+}
+
+/// Whether every E820 entry is fully covered by regions of `map` with the same E820 type.
+pub fn e820_is_subset_of_memory_map(e820: &[E820Entry], map: &[MemoryRegion]) -> bool {
+    e820.iter().all(|entry| {
+        let end = entry.base.saturating_add(entry.length);
+        let mut cursor = entry.base;
+        for region in map {
+            if cursor >= end {
+                break;
+            }
+            if region.start <= cursor
+                && cursor < region.end()
+                && region.kind.e820_type() == entry.region_type
+            {
+                cursor = region.end();
+            }
+        }
+        cursor >= end
+    })
+}
+
+impl Bios {
+    /// Guest-physical memory map for the configured RAM size, the ACPI windows recorded during
+    /// the last POST and the platform's MMIO windows (TPM CRB, second IOAPIC, VBE LFB). The
+    /// INT 15h E820/E801/88h answers are derived from the same layout.
+    pub fn guest_memory_map(&self) -> Vec<MemoryRegion> {
+        let vbe = &self.video.vbe;
+        let mut mmio = vec![(
+            u64::from(vbe.lfb_base),
+            u64::from(vbe.total_memory_64kb_blocks) * 64 * 1024,
+        )];
+        if let Some(base) = self.config.tpm_crb_base {
+            mmio.push((base, TPM_CRB_WINDOW_SIZE));
+        }
+        if let Some(base) = self.config.second_io_apic_base {
+            mmio.push((u64::from(base), MMIO_PAGE_SIZE));
+        }
+        guest_memory_map_with_mmio(
+            self.config.memory_size_bytes,
+            self.acpi_reclaimable,
+            self.acpi_nvs,
+            &mmio,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn assert_sorted_and_non_overlapping(map: &[MemoryRegion]) {
+        for pair in map.windows(2) {
+            assert!(pair[0].end() <= pair[1].start, "overlap: {pair:?}");
+        }
+    }
+
+    #[test]
+    fn small_ram_has_no_pci_hole_or_remap() {
+        let map = guest_memory_map(512 * 1024 * 1024, None, None);
+        assert_sorted_and_non_overlapping(&map);
+        assert!(map.iter().all(|r| !matches!(
+            r.kind,
+            MemoryRegionKind::PciHole | MemoryRegionKind::HighRamRemap
+        )));
+        // The chipset MMIO windows follow RAM; the last RAM region is all of extended memory.
+        assert_eq!(
+            map.iter().rev().find(|r| r.kind.is_ram()).copied(),
+            Some(MemoryRegion {
+                start: ONE_MIB,
+                len: 512 * 1024 * 1024 - ONE_MIB,
+                kind: MemoryRegionKind::UsableRam,
+            })
+        );
+    }
+
+    #[test]
+    fn large_ram_is_remapped_above_4gib_with_acpi_splits() {
+        let total = 4 * GIB;
+        let acpi = (0x0100_0000, 0x1_0000);
+        let nvs = (0x0101_0000, 0x1000);
+        let map = guest_memory_map(total, Some(acpi), Some(nvs));
+        assert_sorted_and_non_overlapping(&map);
+
+        let find = |kind| {
+            map.iter()
+                .filter(|r| r.kind == kind)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            find(MemoryRegionKind::PciHole),
+            vec![MemoryRegion {
+                start: PCI_HOLE_START,
+                len: HIGH_RAM_BASE - PCI_HOLE_START,
+                kind: MemoryRegionKind::PciHole,
+            }]
+        );
+        assert_eq!(
+            find(MemoryRegionKind::HighRamRemap),
+            vec![MemoryRegion {
+                start: HIGH_RAM_BASE,
+                len: total - PCIE_ECAM_BASE,
+                kind: MemoryRegionKind::HighRamRemap,
+            }]
+        );
+        assert_eq!(find(MemoryRegionKind::AcpiReclaim)[0].start, acpi.0);
+        assert_eq!(find(MemoryRegionKind::AcpiNvs)[0].start, nvs.0);
+
+        // All configured RAM is accounted for, minus the EBDA/VGA/ROM window below 1MiB.
+        let ram: u64 = map.iter().filter(|r| r.kind.is_ram()).map(|r| r.len).sum();
+        assert_eq!(ram, total - (ONE_MIB - EBDA_BASE));
+    }
+
+    #[test]
+    fn mmio_windows_are_reserved_explicitly_without_a_pci_hole() {
+        let lfb = (0xE000_0000, 0x100_0000);
+        let tpm = (0xFED4_0000, TPM_CRB_WINDOW_SIZE);
+        let ram_backed_lfb = (0x0080_0000, 0x40_0000);
+        let second_ioapic = (0xFEC0_1000, 0x100);
+        let map = guest_memory_map_with_mmio(
+            512 * 1024 * 1024,
+            None,
+            None,
+            &[lfb, tpm, ram_backed_lfb, second_ioapic],
+        );
+        assert_sorted_and_non_overlapping(&map);
+
+        let mmio: Vec<_> = map
+            .iter()
+            .filter(|r| r.kind == MemoryRegionKind::Mmio && r.start >= ONE_MIB)
+            .map(|r| (r.start, r.len))
+            .collect();
+        assert_eq!(
+            mmio,
+            vec![
+                (PCIE_ECAM_BASE, PCIE_ECAM_SIZE),
+                lfb,
+                // Both IOAPIC pages merge into one window.
+                (0xFEC0_0000, 0x2000),
+                // HPET's 1KiB register block is rounded up to a page.
+                (0xFED0_0000, MMIO_PAGE_SIZE),
+                tpm,
+                (0xFEE0_0000, MMIO_PAGE_SIZE),
+            ]
+        );
+        // A window below the PCI MMIO base stays RAM.
+        let ram: u64 = map.iter().filter(|r| r.kind.is_ram()).map(|r| r.len).sum();
+        assert_eq!(ram, 512 * 1024 * 1024 - (ONE_MIB - EBDA_BASE));
+
+        // With a PCI hole the windows are already covered by it.
+        let map = guest_memory_map_with_mmio(4 * GIB, None, None, &[lfb, tpm]);
+        assert_sorted_and_non_overlapping(&map);
+        assert_eq!(
+            map.iter()
+                .filter(|r| r.kind == MemoryRegionKind::Mmio && r.start >= ONE_MIB)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn ram_physical_ranges_split_at_the_low_ram_window() {
+        assert_eq!(ram_physical_ranges(0, GIB), vec![(0, GIB)]);
+        assert_eq!(
+            ram_physical_ranges(GIB, 2 * GIB),
+            vec![
+                (GIB, LOW_RAM_END - GIB),
+                (HIGH_RAM_BASE, 3 * GIB - LOW_RAM_END)
+            ]
+        );
+        assert_eq!(
+            ram_physical_ranges(LOW_RAM_END + GIB, GIB),
+            vec![(HIGH_RAM_BASE + GIB, GIB)]
+        );
+        assert_eq!(ram_physical_ranges(GIB, 0), vec![]);
+    }
+
+    #[test]
+    fn e820_is_a_subset_of_the_memory_map() {
+        for total in [
+            0,
+            ONE_MIB,
+            64 * 1024 * 1024,
+            PCIE_ECAM_BASE,
+            3 * GIB,
+            6 * GIB,
+        ] {
+            let map = guest_memory_map(total, Some((0x00F0_0000, 0x2_0000)), None);
+            let e820 = e820_from_memory_map(&map);
+            assert!(e820_is_subset_of_memory_map(&e820, &map), "{total:#x}");
+        }
+
+        let map = guest_memory_map(64 * 1024 * 1024, None, None);
+        // RAM over a reserved window is not covered.
+        let bogus = [E820Entry {
+            base: EBDA_BASE - 0x1000,
+            length: 0x2000,
+            region_type: E820_RAM,
+            extended_attributes: 1,
+        }];
+        assert!(!e820_is_subset_of_memory_map(&bogus, &map));
+        // RAM past the end of guest memory is not covered.
+        let bogus = [E820Entry {
+            base: ONE_MIB,
+            length: 64 * 1024 * 1024,
+            region_type: E820_RAM,
+            extended_attributes: 1,
+        }];
+        assert!(!e820_is_subset_of_memory_map(&bogus, &map));
+    }
+}
@@ -16,14 +16,19 @@
 
 mod acpi;
 mod bda_time;
+mod boot;
+mod edd;
 mod eltorito;
 mod int10;
 mod int10_vbe;
 mod int1a;
 mod interrupts;
 mod ivt;
+mod memory_map;
 mod pci;
 mod post;
+mod post_code;
+mod pxe;
 mod rom;
 mod snapshot;
 
@@ -37,12 +42,20 @@ use memory::{DenseMemory, MapError, PhysicalMemoryBus};
 
 use crate::memory::MemoryBus as FirmwareMemoryBus;
 use crate::rtc::{CmosRtc, DateTime};
+use crate::smbios::SmbiosIdentity;
 use crate::video::VideoDevice;
 
-pub use acpi::{AcpiBuilder, AcpiInfo, BiosAcpiError};
+pub use acpi::{AcpiBuilder, AcpiInfo, BiosAcpiError, NumaNodeConfig};
 pub use bda_time::{BdaTime, BDA_MIDNIGHT_FLAG_ADDR, BDA_TICK_COUNT_ADDR, TICKS_PER_DAY};
+pub use boot::{BootEntry, BootMenuState, BOOT_MENU_DEFAULT_TIMEOUT_MS};
+pub use edd::{EddDevicePath, EddInterface};
 pub use interrupts::E820Entry;
+pub use memory_map::{
+    e820_from_memory_map, e820_is_subset_of_memory_map, guest_memory_map,
+    guest_memory_map_with_mmio, ram_physical_ranges, MemoryRegion, MemoryRegionKind,
+};
 pub use pci::{PciConfigSpace, PciDevice};
+pub use post_code::{BiosPostPhase, POST_CODE_PORT};
 pub use rom::build_bios_rom;
 pub use snapshot::BiosSnapshot;
 
@@ -81,6 +94,28 @@ const BDA_KEYBOARD_BUF_END_PTR_OFFSET: u64 = 0x82; // 0x40:0x82 -> 0x482 absolut
 const KEYBOARD_QUEUE_CAPACITY: usize =
     ((BDA_KEYBOARD_BUF_END - BDA_KEYBOARD_BUF_START) / 2).saturating_sub(1) as usize;
 
+/// BDA far pointer (offset at 40:67, segment at 40:69) the BIOS resumes through after a warm reset
+/// with a resume shutdown status (see [`Bios::resume_from_shutdown`]).
+pub const BDA_RESUME_VECTOR_ADDR: u64 = BDA_BASE + 0x67;
+
+// CMOS shutdown status codes (register 0x0F) that resume through the BDA pointer instead of POST.
+/// Send EOI and jump via 40:67.
+pub const SHUTDOWN_STATUS_JMP_EOI: u8 = 0x05;
+/// Jump via 40:67 without EOI.
+pub const SHUTDOWN_STATUS_JMP: u8 = 0x0A;
+/// Load SS:SP from 40:67 and return with `IRET`.
+pub const SHUTDOWN_STATUS_IRET: u8 = 0x0B;
+/// Load SS:SP from 40:67 and return with `RETF`.
+pub const SHUTDOWN_STATUS_RETF: u8 = 0x0C;
+
+/// Whether a CMOS shutdown status byte makes [`Bios::resume_from_shutdown`] resume instead of POST.
+pub fn is_shutdown_resume_status(status: u8) -> bool {
+    matches!(
+        status,
+        SHUTDOWN_STATUS_JMP_EOI | SHUTDOWN_STATUS_JMP | SHUTDOWN_STATUS_IRET | SHUTDOWN_STATUS_RETF
+    )
+}
+
 pub const EBDA_BASE: u64 = 0x0009_F000;
 pub const EBDA_SIZE: usize = 0x1000;
 
@@ -103,6 +138,11 @@ pub const INT15_STUB_OFFSET: u16 = 0xE600;
 pub const INT16_STUB_OFFSET: u16 = 0xE700;
 pub const INT1A_STUB_OFFSET: u16 = 0xE900;
 pub const DEFAULT_INT_STUB_OFFSET: u16 = 0xEF00;
+/// PXE API entry points (`PXENV+` real-mode entry and `!PXE` `EntryPointSP`); see `pxe.rs`.
+pub const PXENV_ENTRY_STUB_OFFSET: u16 = 0xEA00;
+pub const PXE_ENTRY_STUB_OFFSET: u16 = 0xEA08;
+/// Far-return target pushed for a PXE network bootstrap program (`INT 18h` on return).
+pub const PXE_NBP_RETURN_STUB_OFFSET: u16 = 0xEA10;
 /// IVT vector 0x1F: pointer to the 8x8 graphics character table (font).
 pub const VGA_FONT_8X8_OFFSET: u16 = 0xC000;
 /// Offset of the built-in 8x16 font table returned by INT 10h AH=11h AL=30h ("Get Font
@@ -172,6 +212,13 @@ pub(super) enum ElToritoBootMediaType {
     HardDisk = 0x04,
 }
 
+impl ElToritoBootMediaType {
+    /// Size in 512-byte sectors of the emulated diskette, for floppy-emulation media types.
+    pub(super) fn emulated_floppy_sectors(self) -> Option<u64> {
+        eltorito::emulated_floppy_sectors(self as u8)
+    }
+}
+
 /// Cached El Torito CD boot metadata captured during POST.
 ///
 /// When the BIOS boots via an El Torito boot catalog entry, boot images (e.g. ISOLINUX) may query
@@ -232,6 +279,20 @@ pub trait CdromDevice {
     fn size_in_sectors(&self) -> u64;
 }
 
+/// Minimal polled Ethernet interface used by the PXE network boot entry.
+///
+/// POST drives the NIC backend directly (bypassing the guest-visible device model). Frames are
+/// raw Ethernet II frames including the 14-byte header, without FCS.
+pub trait PxeNic {
+    /// MAC address reported by the guest-visible NIC (used as the DHCP client address).
+    fn mac_addr(&self) -> [u8; 6];
+
+    fn transmit(&mut self, frame: &[u8]);
+
+    /// Returns the next received frame, if one is pending.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
+
 /// In-memory block device backed by a `Vec<u8>` of 512-byte sectors.
 #[derive(Debug, Clone)]
 pub struct InMemoryDisk {
@@ -387,24 +448,6 @@ impl FirmwareMemoryBus for BiosMemoryBus<'_> {
     }
 }
 
-/// BIOS boot device class used by the (optional) host-configured boot-order policy.
-///
-/// Note: Aero’s BIOS boot selection is currently driven solely by [`BiosConfig::boot_drive`]
-/// (i.e. an explicit `DL` value). This enum and the related config fields exist primarily for
-/// snapshot forward-compatibility with a future multi-device boot-selection implementation.
-///
-/// This is intentionally a small, stable enum so it can be snapshotted as a compact `u8` list.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum BiosBootDevice {
-    /// Boot from a BIOS fixed disk (`DL=0x80..`).
-    Hdd = 0,
-    /// Boot from an El Torito CD-ROM (`DL=0xE0..`).
-    Cdrom = 1,
-    /// Boot from a floppy disk (`DL=0x00..`).
-    Floppy = 2,
-}
-
 #[derive(Debug, Clone)]
 pub struct BiosConfig {
     /// Total guest RAM size.
@@ -419,6 +462,10 @@ pub struct BiosConfig {
     /// Keeping the default as `0` preserves deterministic tests while letting
     /// runtimes choose stable per-VM identities by overriding this value.
     pub smbios_uuid_seed: u64,
+    /// SMBIOS system/baseboard/chassis identity and OEM strings published during POST.
+    ///
+    /// Unset fields use the built-in Aero defaults (see [`SmbiosIdentity::effective`]).
+    pub smbios: SmbiosIdentity,
     /// Whether to build and publish ACPI tables during POST.
     pub enable_acpi: bool,
     /// Fixed placement contract for ACPI tables written during POST.
@@ -426,21 +473,50 @@ pub struct BiosConfig {
     /// Mapping of PCI PIRQ[A-D] -> platform GSI used by both the ACPI DSDT `_PRT`
     /// and PCI Interrupt Line programming during enumeration.
     pub pirq_to_gsi: [u32; 4],
+    /// MMIO base of a TPM 2.0 CRB interface to describe in ACPI, if the platform has one.
+    ///
+    /// When set (and [`BiosConfig::enable_acpi`] is true), POST publishes a `TPM2` table and a
+    /// `MSFT0101` DSDT device for it. This is platform wiring rather than guest state, so it is
+    /// not part of the BIOS snapshot.
+    pub tpm_crb_base: Option<u64>,
+    /// MMIO base of a second IOAPIC (GSIs 24-47) to describe in the ACPI `MADT`, if the platform
+    /// has one. Platform wiring; not part of the BIOS snapshot.
+    pub second_io_apic_base: Option<u32>,
+    /// NUMA topology to describe via ACPI `SRAT`/`SLIT`, in proximity-domain order.
+    ///
+    /// Node memory is carved out of guest RAM in node order (see [`NumaNodeConfig`]). When empty
+    /// (the default) no NUMA tables are published. Platform wiring; not part of the BIOS snapshot.
+    pub numa_nodes: Vec<NumaNodeConfig>,
     /// Optional override for the VBE linear framebuffer base address reported by the BIOS.
     ///
     /// When unset, the BIOS keeps the default RAM-backed base address
     /// ([`crate::video::vbe::VbeDevice::LFB_BASE_DEFAULT`]).
     pub vbe_lfb_base: Option<u32>,
-
-    /// Optional host-configurable boot-order policy (currently unused by the BIOS boot path).
+    /// I/O bases of the serial ports present on the platform, in COM1..COM4 order.
     ///
-    /// The classic BIOS boot drive number passed in `DL` is stored separately in
-    /// [`BiosConfig::boot_drive`], and **that value is what Aero BIOS currently uses** to select the
-    /// boot path during POST (HDD/floppy boot sector vs El Torito CD boot).
+    /// POST publishes these in the BDA COM port table (consulted by INT 14h) and the INT 11h
+    /// equipment word. Like [`BiosConfig::tpm_crb_base`], this is platform wiring and is not part
+    /// of the BIOS snapshot.
+    pub serial_ports: Vec<u16>,
+    /// Controller attachment of fixed disk `0x80`, reported as the EDD 3.0 device path by
+    /// INT 13h AH=48h.
     ///
-    /// This list is snapshotted/restored to keep the BIOS snapshot format forward-compatible with a
-    /// future “try devices in order” selection algorithm.
-    pub boot_order: Vec<BiosBootDevice>,
+    /// When unset, AH=48h still returns the EDD 3.0 table but with zeroed interface/device paths.
+    /// Platform wiring; not part of the BIOS snapshot.
+    pub hdd_device_path: Option<EddDevicePath>,
+    /// Controller attachment of the CD-ROM drive (`0xE0..=0xEF`); see
+    /// [`BiosConfig::hdd_device_path`].
+    pub cdrom_device_path: Option<EddDevicePath>,
+
+    /// Optional host-configurable boot order.
+    ///
+    /// When non-empty, POST tries each entry in turn and boots the first one whose media is
+    /// present and bootable (MBR signature for disks/floppies, El Torito catalog for CD-ROMs),
+    /// falling through to the next entry on failure.
+    ///
+    /// When empty (the default), POST uses the classic single-drive policy: optional CD-first
+    /// (see [`BiosConfig::boot_from_cd_if_present`]) followed by [`BiosConfig::boot_drive`].
+    pub boot_order: Vec<BootEntry>,
     /// BIOS drive number to use when booting from a CD-ROM device class (boot-order policy).
     ///
     /// The conventional range for El Torito CD-ROM boot devices is `0xE0..=0xEF`.
@@ -454,6 +530,13 @@ pub struct BiosConfig {
     /// CD-ROM first (using [`BiosConfig::cd_boot_drive`]) and fall back to the configured
     /// [`BiosConfig::boot_drive`] on failure.
     pub boot_from_cd_if_present: bool,
+    /// Render the interactive boot menu during POST instead of booting immediately.
+    ///
+    /// This is a one-shot host request (see [`Bios::poll_boot_menu`]); it is not snapshotted as
+    /// part of the configuration.
+    pub boot_menu: bool,
+    /// Time the boot menu waits for a selection before using the configured boot order.
+    pub boot_menu_timeout_ms: u32,
 }
 
 impl Default for BiosConfig {
@@ -469,14 +552,23 @@ impl Default for BiosConfig {
             boot_drive: 0x80,
             cpu_count: 1,
             smbios_uuid_seed: 0,
+            smbios: SmbiosIdentity::default(),
             enable_acpi: true,
             acpi_placement,
             // Match the default routing in `aero_acpi::AcpiConfig`.
             pirq_to_gsi: aero_pci_routing::DEFAULT_PIRQ_TO_GSI,
+            tpm_crb_base: None,
+            second_io_apic_base: None,
+            numa_nodes: Vec::new(),
             vbe_lfb_base: None,
-            boot_order: vec![BiosBootDevice::Hdd],
+            serial_ports: vec![0x3F8],
+            hdd_device_path: None,
+            cdrom_device_path: None,
+            boot_order: Vec::new(),
             cd_boot_drive: 0xE0,
             boot_from_cd_if_present: false,
+            boot_menu: false,
+            boot_menu_timeout_ms: BOOT_MENU_DEFAULT_TIMEOUT_MS,
         }
     }
 }
@@ -534,6 +626,10 @@ pub struct Bios {
     last_int13_status: u8,
     /// El Torito CD boot metadata captured during POST.
     el_torito_boot_info: Option<ElToritoBootInfo>,
+    /// Boot-order entry that POST (or the boot menu) transferred control to.
+    booted_entry: Option<BootEntry>,
+    /// Boot menu waiting for a host-injected selection.
+    boot_menu: Option<BootMenuState>,
 
     /// RSDP physical address (if ACPI tables were built).
     rsdp_addr: Option<u64>,
@@ -542,6 +638,9 @@ pub struct Bios {
 
     /// SMBIOS Entry Point Structure physical address (if SMBIOS tables were built).
     smbios_eps_addr: Option<u32>,
+
+    /// POST codes not yet drained by [`Bios::take_post_codes`].
+    post_codes: Vec<u8>,
 }
 
 impl Bios {
@@ -571,10 +670,13 @@ impl Bios {
             tty_output_start: 0,
             last_int13_status: 0,
             el_torito_boot_info: None,
+            booted_entry: None,
+            boot_menu: None,
             rsdp_addr: None,
             acpi_reclaimable: None,
             acpi_nvs: None,
             smbios_eps_addr: None,
+            post_codes: Vec::new(),
         }
     }
 
@@ -610,6 +712,24 @@ impl Bios {
         self.el_torito_boot_info.is_some()
     }
 
+    /// Returns the boot-order entry the most recent POST booted from, if any.
+    pub fn booted_entry(&self) -> Option<BootEntry> {
+        self.booted_entry
+    }
+
+    /// If `drive` is the El Torito floppy-emulation drive, returns the boot image location on the
+    /// CD as `(start_lba, sector_count)` in 512-byte sectors.
+    fn el_torito_emulated_floppy(&self, drive: u8) -> Option<(u64, u64)> {
+        let info = self.el_torito_boot_info?;
+        if info.boot_drive != drive {
+            return None;
+        }
+        let sectors = info.media_type.emulated_floppy_sectors()?;
+        let start_lba =
+            u64::from(info.boot_image_lba?) * (CDROM_SECTOR_SIZE / BIOS_SECTOR_SIZE) as u64;
+        Some((start_lba, sectors))
+    }
+
     /// Returns the configured BIOS boot drive number used when transferring control to the boot
     /// sector / El Torito boot image.
     pub fn boot_drive(&self) -> u8 {
@@ -662,6 +782,21 @@ impl Bios {
         self.config.cd_boot_drive = cd_boot_drive;
     }
 
+    /// Returns the configured boot order (empty means the classic `boot_drive` policy).
+    pub fn boot_order(&self) -> &[BootEntry] {
+        &self.config.boot_order
+    }
+
+    /// Set the boot order tried by POST. Takes effect on the next POST.
+    pub fn set_boot_order(&mut self, boot_order: Vec<BootEntry>) {
+        self.config.boot_order = boot_order;
+    }
+
+    /// Set how long the boot menu waits for a selection. Takes effect on the next POST.
+    pub fn set_boot_menu_timeout_ms(&mut self, timeout_ms: u32) {
+        self.config.boot_menu_timeout_ms = timeout_ms;
+    }
+
     pub fn tty_output(&self) -> &[u8] {
         let start = self.tty_output_start.min(self.tty_output.len());
         &self.tty_output[start..]
@@ -687,6 +822,16 @@ impl Bios {
         self.smbios_eps_addr
     }
 
+    /// SMBIOS identity values published by POST (sanitized, with defaults filled in).
+    pub fn smbios_identity(&self) -> SmbiosIdentity {
+        self.config.smbios.effective()
+    }
+
+    /// Set the SMBIOS identity strings. Takes effect on the next POST.
+    pub fn set_smbios_identity(&mut self, identity: SmbiosIdentity) {
+        self.config.smbios = identity;
+    }
+
     pub fn pci_devices(&self) -> &[PciDevice] {
         &self.pci_devices
     }
@@ -729,7 +874,7 @@ impl Bios {
         disk: &mut dyn BlockDevice,
         cdrom: Option<&mut dyn CdromDevice>,
     ) {
-        self.post_impl(cpu, bus, disk, cdrom, None);
+        self.post_impl(cpu, bus, disk, None, cdrom, None, None);
     }
 
     pub fn post_with_pci(
@@ -740,7 +885,45 @@ impl Bios {
         cdrom: Option<&mut dyn CdromDevice>,
         pci: Option<&mut dyn PciConfigSpace>,
     ) {
-        self.post_impl(cpu, bus, disk, cdrom, pci);
+        self.post_impl(cpu, bus, disk, None, cdrom, None, pci);
+    }
+
+    /// Run POST with an optional dedicated floppy drive (A:) and network adapter alongside the
+    /// fixed disk and CD-ROM.
+    ///
+    /// The floppy backs [`BootEntry::Floppy`] in the boot order, and a floppy
+    /// [`BiosConfig::boot_drive`] under the classic policy. The NIC backs
+    /// [`BootEntry::Network`] (PXE) and is only used during POST.
+    #[allow(clippy::too_many_arguments)]
+    pub fn post_with_devices(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        disk: &mut dyn BlockDevice,
+        floppy: Option<&mut dyn BlockDevice>,
+        cdrom: Option<&mut dyn CdromDevice>,
+        nic: Option<&mut dyn PxeNic>,
+        pci: Option<&mut dyn PciConfigSpace>,
+    ) {
+        self.post_impl(cpu, bus, disk, floppy, cdrom, nic, pci);
+    }
+
+    /// Warm-reset entry for the CMOS shutdown status byte (register `0x0F`).
+    ///
+    /// Returns `false` without touching `cpu` when `status` does not request a resume, in which
+    /// case the caller runs POST as usual. Otherwise the BIOS ROM is remapped, PCI interrupt lines
+    /// are reprogrammed and the CPU continues in real mode through the pointer at
+    /// [`BDA_RESUME_VECTOR_ADDR`], with interrupts disabled unless an `IRET` frame re-enables
+    /// them. The rest of the firmware state (IVT, BDA, E820 map) is left as the interrupted boot
+    /// set it up.
+    pub fn resume_from_shutdown(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        pci: Option<&mut dyn PciConfigSpace>,
+        status: u8,
+    ) -> bool {
+        self.resume_impl(cpu, bus, pci, status)
     }
 
     pub fn post_with_cdrom(
@@ -1118,6 +1301,55 @@ mod tests {
         assert!(cpu.get_flag(RFLAGS_IF));
     }
 
+    #[test]
+    fn post_emits_phase_codes_in_order() {
+        let mut bios = Bios::new(BiosConfig {
+            memory_size_bytes: 16 * 1024 * 1024,
+            boot_drive: 0x80,
+            ..BiosConfig::default()
+        });
+        let mut cpu = CpuState::new(aero_cpu_core::state::CpuMode::Real);
+        let mut mem = TestMemory::new(16 * 1024 * 1024);
+        let mut disk = InMemoryDisk::from_boot_sector(boot_sector(0));
+
+        bios.post(&mut cpu, &mut mem, &mut disk, None);
+
+        let phases: Vec<_> = bios
+            .take_post_codes()
+            .into_iter()
+            .map(|code| BiosPostPhase::from_code(code).unwrap())
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                BiosPostPhase::CpuInit,
+                BiosPostPhase::MemoryInit,
+                BiosPostPhase::VideoInit,
+                BiosPostPhase::InterruptVectors,
+                BiosPostPhase::Smbios,
+                BiosPostPhase::AcpiTables,
+                BiosPostPhase::BootDeviceSelection,
+                BiosPostPhase::BootHandoff,
+            ]
+        );
+        assert!(bios.take_post_codes().is_empty());
+
+        // An unbootable disk ends POST with the failure code instead of the handoff.
+        let mut unbootable = boot_sector(0);
+        unbootable[510] = 0;
+        let mut disk = InMemoryDisk::from_boot_sector(unbootable);
+        bios.post(&mut cpu, &mut mem, &mut disk, None);
+        let codes = bios.take_post_codes();
+        assert_eq!(
+            codes[codes.len() - 2..],
+            [
+                BiosPostPhase::BootDeviceSelection.code(),
+                BiosPostPhase::BootFailure.code()
+            ]
+        );
+        assert!(cpu.halted);
+    }
+
     #[test]
     fn post_maps_bios_rom_at_the_reset_vector_alias() {
         let mut bios = Bios::new(BiosConfig::default());
@@ -3,19 +3,24 @@ use std::sync::Arc;
 use aero_cpu_core::state::{gpr, CpuMode, CpuState, RFLAGS_IF};
 
 use super::{
-    eltorito, ivt, pci::PciConfigSpace, rom, set_real_mode_seg, Bios, BiosBus, BiosMemoryBus,
-    BlockDevice, CdromDevice, DiskError, ElToritoBootInfo, ElToritoBootMediaType, BIOS_ALIAS_BASE,
-    BIOS_BASE, BIOS_SECTOR_SIZE, BIOS_SEGMENT, CDROM_SECTOR_SIZE, EBDA_BASE,
+    eltorito, is_shutdown_resume_status, ivt, pci::PciConfigSpace, rom, set_real_mode_seg, Bios,
+    BiosBus, BiosMemoryBus, BiosPostPhase, BlockDevice, BootEntry, CdromDevice, DiskError,
+    ElToritoBootInfo, ElToritoBootMediaType, PxeNic, BDA_RESUME_VECTOR_ADDR, BIOS_ALIAS_BASE,
+    BIOS_BASE, BIOS_SECTOR_SIZE, BIOS_SEGMENT, CDROM_SECTOR_SIZE, EBDA_BASE, SHUTDOWN_STATUS_IRET,
+    SHUTDOWN_STATUS_RETF,
 };
 use crate::smbios::{SmbiosConfig, SmbiosTables};
 
 impl Bios {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn post_impl(
         &mut self,
         cpu: &mut CpuState,
         bus: &mut dyn BiosBus,
         disk: &mut dyn BlockDevice,
+        floppy: Option<&mut dyn BlockDevice>,
         cdrom: Option<&mut dyn CdromDevice>,
+        nic: Option<&mut dyn PxeNic>,
         pci: Option<&mut dyn PciConfigSpace>,
     ) {
         // Reset transient POST state.
@@ -27,22 +32,16 @@ impl Bios {
         self.smbios_eps_addr = None;
         self.last_int13_status = 0;
         self.el_torito_boot_info = None;
+        self.booted_entry = None;
+        self.boot_menu = None;
         self.unhandled_interrupt_log_count = 0;
         self.clear_tty_output();
 
         // 0) Install ROM stubs (read-only).
-        //
-        // The BIOS ROM is mapped twice:
-        // - `BIOS_BASE` (the conventional `F0000..=FFFFF` real-mode window)
-        // - `BIOS_ALIAS_BASE` (the 32-bit reset-vector alias at `FFFF_0000..=FFFF_FFFF`)
-        //
-        // Note: `BIOS_ALIAS_BASE` is outside typical guest RAM. Bus implementations that only
-        // model RAM may need to treat ROM mappings as sparse.
-        let rom_image: Arc<[u8]> = rom::build_bios_rom().into();
-        bus.map_rom(BIOS_BASE, rom_image.clone());
-        bus.map_rom(BIOS_ALIAS_BASE, rom_image);
+        map_bios_rom(bus);
 
         // 1) Real-mode CPU init: interrupts disabled during POST.
+        self.post_code(BiosPostPhase::CpuInit);
         cpu.mode = CpuMode::Real;
         cpu.halted = false;
         cpu.clear_pending_bios_int();
@@ -57,7 +56,9 @@ impl Bios {
         cpu.set_rip(super::RESET_VECTOR_OFFSET); // conventional reset vector within F000 segment
 
         // 2) BDA/EBDA: reserve a 4KiB EBDA page below 1MiB and advertise base memory size.
+        self.post_code(BiosPostPhase::MemoryInit);
         ivt::init_bda(bus, self.config.boot_drive);
+        ivt::init_bda_serial_ports(bus, &self.config.serial_ports);
         // If a CD-ROM device is attached *in addition to* the primary HDD `disk` (via
         // `post_with_cdrom`), ensure we still advertise HDD0 in the BDA even when the configured
         // boot drive is a CD-ROM (0xE0..=0xEF).
@@ -67,21 +68,25 @@ impl Bios {
         self.init(bus);
         // Initialize VGA text mode state (mode 03h) so software querying BDA/INT 10h gets sane
         // defaults without needing to explicitly set a mode first.
+        self.post_code(BiosPostPhase::VideoInit);
         self.video
             .vga
             .set_text_mode_03h(&mut BiosMemoryBus::new(bus), true);
         self.video_mode = 0x03;
 
         // 3) Interrupt Vector Table.
+        self.post_code(BiosPostPhase::InterruptVectors);
         ivt::init_ivt(bus);
 
         // 4) SMBIOS: publish the SMBIOS EPS in the EBDA so Windows can discover it.
         //
         // Keep the EPS within the first 1KiB of EBDA (per spec) while avoiding the RSDP slot.
+        self.post_code(BiosPostPhase::Smbios);
         let smbios_cfg = SmbiosConfig {
             ram_bytes: self.config.memory_size_bytes,
             cpu_count: self.config.cpu_count.max(1),
             uuid_seed: self.config.smbios_uuid_seed,
+            identity: self.config.smbios.clone(),
             eps_addr: Some((EBDA_BASE + 0x200) as u32),
             table_addr: Some((EBDA_BASE + 0x400) as u32),
         };
@@ -96,16 +101,21 @@ impl Bios {
 
         // 6) Optional PCI enumeration + deterministic IRQ routing (must match ACPI `_PRT`).
         if let Some(pci) = pci {
+            self.post_code(BiosPostPhase::PciEnumeration);
             self.enumerate_pci(pci);
         }
 
         // 7) ACPI tables (generated via `aero-acpi`).
         if self.config.enable_acpi {
+            self.post_code(BiosPostPhase::AcpiTables);
             match self.acpi_builder.build_and_write(
                 bus,
                 self.config.memory_size_bytes,
                 self.config.cpu_count,
                 self.config.pirq_to_gsi,
+                self.config.tpm_crb_base,
+                self.config.second_io_apic_base,
+                &self.config.numa_nodes,
                 self.config.acpi_placement,
             ) {
                 Ok(info) => {
@@ -120,13 +130,87 @@ impl Bios {
             };
         }
 
+        // The E820 map handed to the guest must stay a view of the host-visible memory map.
+        debug_assert!(
+            {
+                let map = self.guest_memory_map();
+                super::e820_is_subset_of_memory_map(&super::e820_from_memory_map(&map), &map)
+            },
+            "BIOS: E820 map diverged from the guest memory map"
+        );
+
         // 8) Re-enable interrupts after POST.
         cpu.rflags |= RFLAGS_IF;
 
-        // 9) Boot: load sector 0 to 0x7C00 and jump.
-        if let Err(msg) = self.boot(cpu, bus, disk, cdrom) {
-            self.bios_panic(cpu, bus, msg);
+        // 9) Boot: either park in the boot menu, or load the boot image and jump.
+        if self.config.boot_menu {
+            self.post_code(BiosPostPhase::BootMenu);
+            self.show_boot_menu(
+                cpu,
+                bus,
+                disk,
+                floppy.is_some(),
+                cdrom.is_some(),
+                nic.is_some(),
+            );
+            return;
+        }
+        self.post_code(BiosPostPhase::BootDeviceSelection);
+        match self.boot(cpu, bus, disk, floppy, cdrom, nic) {
+            Ok(()) => self.post_code(BiosPostPhase::BootHandoff),
+            Err(msg) => self.bios_panic(cpu, bus, msg),
+        }
+    }
+
+    pub(super) fn resume_impl(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        pci: Option<&mut dyn PciConfigSpace>,
+        status: u8,
+    ) -> bool {
+        if !is_shutdown_resume_status(status) {
+            return false;
+        }
+
+        self.post_code(BiosPostPhase::ShutdownResume);
+        map_bios_rom(bus);
+        if let Some(pci) = pci {
+            self.enumerate_pci(pci);
         }
+
+        cpu.mode = CpuMode::Real;
+        cpu.halted = false;
+        cpu.clear_pending_bios_int();
+        cpu.set_rflags(0);
+        cpu.a20_enabled = bus.a20_enabled();
+
+        // The PICs were re-initialized by the platform reset, so the EOI requested by code 0x05
+        // has nothing left to acknowledge.
+        let offset = bus.read_u16(BDA_RESUME_VECTOR_ADDR);
+        let segment = bus.read_u16(BDA_RESUME_VECTOR_ADDR + 2);
+        let (cs, ip) = if matches!(status, SHUTDOWN_STATUS_IRET | SHUTDOWN_STATUS_RETF) {
+            // 40:67 holds the caller's SS:SP; pop the return frame from it.
+            set_real_mode_seg(&mut cpu.segments.ss, segment);
+            let stack_base = u64::from(segment) << 4;
+            let mut sp = offset;
+            let mut frame = [0u16; 3];
+            let words = if status == SHUTDOWN_STATUS_IRET { 3 } else { 2 };
+            for word in &mut frame[..words] {
+                *word = bus.read_u16(stack_base + u64::from(sp));
+                sp = sp.wrapping_add(2);
+            }
+            if status == SHUTDOWN_STATUS_IRET {
+                cpu.set_rflags(u64::from(frame[2]));
+            }
+            cpu.gpr[gpr::RSP] = u64::from(sp);
+            (frame[1], frame[0])
+        } else {
+            (segment, offset)
+        };
+        set_real_mode_seg(&mut cpu.segments.cs, cs);
+        cpu.set_rip(u64::from(ip));
+        true
     }
 
     /// Load the configured boot device into memory and initialize the real-mode CPU state
@@ -150,6 +234,11 @@ impl Bios {
         } else {
             self.load_mbr_boot_sector(bus, disk)?
         };
+        // Under El Torito floppy emulation the boot image runs from the emulated drive (A:).
+        let boot_drive = self
+            .el_torito_boot_info
+            .filter(|_| (0xE0..=0xEF).contains(&boot_drive))
+            .map_or(boot_drive, |info| info.boot_drive);
 
         // Register setup per BIOS conventions.
         cpu.gpr[gpr::RAX] = 0;
@@ -174,8 +263,22 @@ impl Bios {
         cpu: &mut CpuState,
         bus: &mut dyn BiosBus,
         disk: &mut dyn BlockDevice,
+        floppy: Option<&mut dyn BlockDevice>,
         mut cdrom: Option<&mut dyn CdromDevice>,
+        nic: Option<&mut dyn PxeNic>,
     ) -> Result<(), &'static str> {
+        // Multi-entry boot order: probe each device in turn (see `boot.rs`).
+        if !self.config.boot_order.is_empty() {
+            return self.boot_in_order(None, cpu, bus, disk, floppy, cdrom, nic);
+        }
+
+        // Classic policy: a floppy `boot_drive` boots from the dedicated floppy device when the
+        // caller provides one, otherwise from `disk`.
+        let disk: &mut dyn BlockDevice = match floppy {
+            Some(floppy) if self.config.boot_drive < 0x80 => floppy,
+            _ => disk,
+        };
+
         fn boot_current(
             bios: &mut Bios,
             cpu: &mut CpuState,
@@ -216,11 +319,13 @@ impl Bios {
             // Always restore the configured fallback boot drive.
             self.config.boot_drive = fallback_drive;
             if cd_res.is_ok() {
+                self.booted_entry = BootEntry::from_drive(cd_boot_drive);
                 return Ok(());
             }
         }
 
         boot_current(self, cpu, bus, disk, &mut cdrom)?;
+        self.booted_entry = BootEntry::from_drive(fallback_drive);
 
         Ok(())
     }
@@ -249,6 +354,12 @@ impl Bios {
     ) -> Result<(u16, u16), &'static str> {
         let parsed = eltorito::parse_boot_image(disk)?;
         let entry = parsed.image;
+        let media_type = match entry.media_type {
+            0x01 => ElToritoBootMediaType::Floppy1200KiB,
+            0x02 => ElToritoBootMediaType::Floppy1440KiB,
+            0x03 => ElToritoBootMediaType::Floppy2880KiB,
+            _ => ElToritoBootMediaType::NoEmulation,
+        };
         let start_lba = u64::from(entry.load_rba)
             .checked_mul(4)
             .ok_or("boot image read error")?;
@@ -261,13 +372,25 @@ impl Bios {
             bus.write_physical(dst + i * BIOS_SECTOR_SIZE as u64, &buf);
         }
 
+        // Floppy emulation: the boot image becomes drive A: (`DL=0x00`) for INT 13h, while the
+        // CD itself stays reachable at its own drive number. Advertise the emulated drive in the
+        // BDA equipment word so INT 11h/INT 13h see a floppy.
+        let boot_drive = if media_type == ElToritoBootMediaType::NoEmulation {
+            self.config.boot_drive
+        } else {
+            let equip = bus.read_u16(super::BDA_BASE + 0x10);
+            bus.write_u16(super::BDA_BASE + 0x10, equip | 0x0001);
+            0x00
+        };
+
         // Cache boot metadata for INT 13h AH=4Bh ("El Torito disk emulation services").
         //
         // This is commonly used by CD boot loaders such as ISOLINUX, even when running in
-        // "no emulation" mode.
+        // "no emulation" mode. In floppy emulation mode it also drives INT 13h's mapping of the
+        // emulated drive onto the boot image.
         self.el_torito_boot_info = Some(ElToritoBootInfo {
-            media_type: ElToritoBootMediaType::NoEmulation,
-            boot_drive: self.config.boot_drive,
+            media_type,
+            boot_drive,
             controller_index: 0,
             boot_catalog_lba: Some(parsed.boot_catalog_lba),
             boot_image_lba: Some(entry.load_rba),
@@ -292,6 +415,9 @@ impl Bios {
 
         let parsed = eltorito::parse_boot_image(disk)?;
         let entry = parsed.image;
+        if entry.media_type != eltorito::MEDIA_NO_EMULATION {
+            return Err("El Torito floppy emulation is not supported via INT 19h");
+        }
 
         // Refresh El Torito boot metadata so callers probing INT 13h AH=4Bh after a soft reboot see
         // a consistent view of the active boot image.
@@ -347,7 +473,7 @@ impl Bios {
         Ok(())
     }
 
-    fn bios_diag(&mut self, bus: &mut dyn BiosBus, msg: &str) {
+    pub(super) fn bios_diag(&mut self, bus: &mut dyn BiosBus, msg: &str) {
         // Record the message in the TTY buffer for programmatic inspection.
         self.push_tty_bytes(msg.as_bytes());
         let needs_newline = msg.as_bytes().last().is_none_or(|b| *b != b'\n');
@@ -378,6 +504,7 @@ impl Bios {
         bus: &mut dyn BiosBus,
         msg: &'static str,
     ) {
+        self.post_code(BiosPostPhase::BootFailure);
         // Record the message in the TTY buffer for programmatic inspection.
         self.push_tty_bytes(msg.as_bytes());
         let needs_newline = msg.as_bytes().last().is_none_or(|b| *b != b'\n');
@@ -399,14 +526,14 @@ impl Bios {
 ///
 /// This is used so the existing El Torito + INT 13h CD paths (which operate on 512-byte BIOS
 /// sectors) can be driven by a real ISO image without loading it into memory.
-struct CdromAsBlockDevice<'a> {
+pub(super) struct CdromAsBlockDevice<'a> {
     cdrom: &'a mut dyn CdromDevice,
     cached_lba: Option<u64>,
     cached: [u8; CDROM_SECTOR_SIZE],
 }
 
 impl<'a> CdromAsBlockDevice<'a> {
-    fn new(cdrom: &'a mut dyn CdromDevice) -> Self {
+    pub(super) fn new(cdrom: &'a mut dyn CdromDevice) -> Self {
         Self {
             cdrom,
             cached_lba: None,
@@ -436,10 +563,23 @@ impl BlockDevice for CdromAsBlockDevice<'_> {
     }
 }
 
+/// Map the BIOS ROM twice:
+/// - `BIOS_BASE` (the conventional `F0000..=FFFFF` real-mode window)
+/// - `BIOS_ALIAS_BASE` (the 32-bit reset-vector alias at `FFFF_0000..=FFFF_FFFF`)
+///
+/// Note: `BIOS_ALIAS_BASE` is outside typical guest RAM. Bus implementations that only model RAM
+/// may need to treat ROM mappings as sparse.
+fn map_bios_rom(bus: &mut dyn BiosBus) {
+    let rom_image: Arc<[u8]> = rom::build_bios_rom().into();
+    bus.map_rom(BIOS_BASE, rom_image.clone());
+    bus.map_rom(BIOS_ALIAS_BASE, rom_image);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::bios::{BiosConfig, InMemoryDisk, TestMemory};
+    use crate::bios::{BiosConfig, InMemoryDisk, TestMemory, SHUTDOWN_STATUS_JMP};
+    use memory::MemoryBus as _;
 
     #[test]
     fn post_panic_renders_message_to_vga_text_buffer() {
@@ -466,4 +606,33 @@ mod tests {
         let chars: Vec<u8> = vga.iter().step_by(2).copied().collect();
         assert!(chars.windows(msg.len()).any(|window| window == msg));
     }
+
+    #[test]
+    fn resume_from_shutdown_follows_bda_pointer() {
+        let mut bios = Bios::new(BiosConfig::default());
+        let mut mem = TestMemory::new(2 * 1024 * 1024);
+
+        let mut cpu = CpuState::new(CpuMode::Real);
+        assert!(!bios.resume_from_shutdown(&mut cpu, &mut mem, None, 0x00));
+        assert_eq!(cpu.rip(), CpuState::new(CpuMode::Real).rip());
+
+        mem.write_u16(BDA_RESUME_VECTOR_ADDR, 0x0123);
+        mem.write_u16(BDA_RESUME_VECTOR_ADDR + 2, 0x2000);
+        assert!(bios.resume_from_shutdown(&mut cpu, &mut mem, None, SHUTDOWN_STATUS_JMP));
+        assert_eq!(cpu.segments.cs.selector, 0x2000);
+        assert_eq!(cpu.rip(), 0x0123);
+        assert_eq!(cpu.rflags() & RFLAGS_IF, 0);
+
+        // 0x0B: 40:67 is SS:SP of an IRET frame (IP, CS, FLAGS).
+        mem.write_u16(0x2_0123, 0x4567);
+        mem.write_u16(0x2_0125, 0x1000);
+        mem.write_u16(0x2_0127, 0x0202);
+        let mut cpu = CpuState::new(CpuMode::Real);
+        assert!(bios.resume_from_shutdown(&mut cpu, &mut mem, None, SHUTDOWN_STATUS_IRET));
+        assert_eq!(cpu.segments.cs.selector, 0x1000);
+        assert_eq!(cpu.rip(), 0x4567);
+        assert_eq!(cpu.segments.ss.selector, 0x2000);
+        assert_eq!(cpu.gpr[gpr::RSP], 0x0129);
+        assert_ne!(cpu.rflags() & RFLAGS_IF, 0);
+    }
 }
@@ -0,0 +1,97 @@
+//! POST progress codes (the values real BIOSes write to I/O port `0x80`).
+//!
+//! The HLE BIOS runs on the host and never executes `OUT` instructions, so it queues a code at
+//! the start of each major POST phase instead ([`super::Bios::take_post_codes`]). The machine
+//! replays the queue into its port `0x80` device, where the codes land in the same history as
+//! guest writes.
+
+use super::Bios;
+
+/// I/O port real BIOSes write POST codes to.
+pub const POST_CODE_PORT: u16 = 0x80;
+
+/// Major BIOS POST phases, in the order a normal POST reaches them. The discriminant is the code
+/// written to [`POST_CODE_PORT`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum BiosPostPhase {
+    /// ROM mapped, real-mode CPU state initialized.
+    CpuInit = 0x01,
+    /// Resuming through the CMOS shutdown status byte instead of running POST.
+    ShutdownResume = 0x05,
+    /// BDA/EBDA and base memory size set up.
+    MemoryInit = 0x10,
+    /// Text mode 03h programmed.
+    VideoInit = 0x20,
+    /// Interrupt vector table installed.
+    InterruptVectors = 0x30,
+    /// SMBIOS tables published.
+    Smbios = 0x38,
+    /// PCI devices enumerated and their IRQ routing programmed.
+    PciEnumeration = 0x40,
+    /// ACPI tables built.
+    AcpiTables = 0x50,
+    /// Boot menu displayed, waiting for a selection.
+    BootMenu = 0x60,
+    /// Probing the boot order for a bootable device.
+    BootDeviceSelection = 0x70,
+    /// Boot image loaded; control passes to it.
+    BootHandoff = 0xA0,
+    /// No device booted; the BIOS halted with a message.
+    BootFailure = 0xEE,
+}
+
+impl BiosPostPhase {
+    pub const ALL: [Self; 12] = [
+        Self::CpuInit,
+        Self::ShutdownResume,
+        Self::MemoryInit,
+        Self::VideoInit,
+        Self::InterruptVectors,
+        Self::Smbios,
+        Self::PciEnumeration,
+        Self::AcpiTables,
+        Self::BootMenu,
+        Self::BootDeviceSelection,
+        Self::BootHandoff,
+        Self::BootFailure,
+    ];
+
+    pub const fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Decode a POST code; `None` for codes the Aero BIOS never emits (e.g. guest writes).
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|phase| phase.code() == code)
+    }
+
+    /// Short human-readable description for host UIs.
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::CpuInit => "CPU init",
+            Self::ShutdownResume => "shutdown resume",
+            Self::MemoryInit => "memory init",
+            Self::VideoInit => "video init",
+            Self::InterruptVectors => "interrupt vectors",
+            Self::Smbios => "SMBIOS tables",
+            Self::PciEnumeration => "PCI enumeration",
+            Self::AcpiTables => "ACPI tables",
+            Self::BootMenu => "boot menu",
+            Self::BootDeviceSelection => "boot device selection",
+            Self::BootHandoff => "boot handoff",
+            Self::BootFailure => "boot failure",
+        }
+    }
+}
+
+impl Bios {
+    pub(super) fn post_code(&mut self, phase: BiosPostPhase) {
+        self.post_codes.push(phase.code());
+    }
+
+    /// Drain the POST codes queued since the previous call, oldest first.
+    pub fn take_post_codes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.post_codes)
+    }
+}
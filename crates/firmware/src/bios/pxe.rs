@@ -0,0 +1,861 @@
+//! Minimal PXE network boot: DHCP + TFTP over a polled NIC, plus just enough of the PXE API for
+//! an iPXE network bootstrap program (NBP) to take over.
+//!
+//! When [`BootEntry::Network`](super::BootEntry::Network) is reached in the boot order, POST:
+//! 1. broadcasts a PXE `DHCPDISCOVER`, then requests the offered lease,
+//! 2. resolves the boot server's MAC address (reusing the DHCP server's, or via ARP),
+//! 3. downloads the boot file named by the lease over TFTP to `0000:7C00`,
+//! 4. publishes `PXENV+`/`!PXE` structures (plus the cached DHCP packets) in a 4KiB block carved
+//!    out of the top of conventional memory, and jumps to the NBP with `ES:BX -> PXENV+` and
+//!    `SS:SP+4 -> !PXE`.
+//!
+//! POST runs synchronously, so timeouts are measured in NIC polls rather than wall-clock time:
+//! every request is retransmitted [`ATTEMPTS`] times, each waiting for at most
+//! [`POLLS_PER_ATTEMPT`] received-frame polls. A missing DHCP/TFTP server therefore fails the
+//! entry and POST falls through to the next boot device.
+//!
+//! PXE API calls reach the BIOS through tiny ROM stubs that re-enter via `INT 1Ah` (`AX=5651h`
+//! for the `PXENV+` entry point, `AX=5652h` for `!PXE`). Only the calls iPXE issues while
+//! chainloading are implemented (cached DHCP packets, NIC information and the unload/shutdown
+//! sequence); everything else reports `PXENV_STATUS_UNSUPPORTED`. The UNDI and TFTP APIs are not
+//! provided, so NBPs that keep using the firmware's network stack (e.g. `undionly.kpxe`,
+//! PXELINUX) are out of scope.
+
+use aero_cpu_core::state::{gpr, mask_bits, CpuState, FLAG_CF};
+
+use super::{
+    set_real_mode_seg, Bios, BiosBus, PxeNic, BDA_BASE, BIOS_SEGMENT, EBDA_BASE,
+    PXENV_ENTRY_STUB_OFFSET, PXE_ENTRY_STUB_OFFSET, PXE_NBP_RETURN_STUB_OFFSET,
+};
+
+/// Retransmissions of each DHCP/ARP/TFTP request before giving up.
+const ATTEMPTS: u32 = 4;
+/// Received-frame polls per attempt (counted whether or not a frame is pending).
+const POLLS_PER_ATTEMPT: u32 = 4096;
+
+/// Conventional memory reserved for the PXE structures (directly below the EBDA).
+const PXE_DATA_BASE: u64 = EBDA_BASE - 0x1000;
+const PXE_DATA_SEGMENT: u16 = (PXE_DATA_BASE / 16) as u16;
+const PXENV_OFFSET: u16 = 0x000;
+const PXE_OFFSET: u16 = 0x040;
+/// Private slot holding the cached DHCPDISCOVER / DHCPACK lengths (two `u16`s).
+const CACHED_LEN_OFFSET: u16 = 0x0F0;
+const DISCOVER_OFFSET: u16 = 0x100;
+const DISCOVER_LIMIT: u16 = 0x300;
+const ACK_OFFSET: u16 = 0x400;
+const ACK_LIMIT: u16 = 0xC00;
+
+const BDA_MEM_SIZE_KB_OFFSET: u64 = 0x13;
+
+const NBP_LOAD_ADDR: u64 = 0x7C00;
+const NBP_MAX_SIZE: usize = (PXE_DATA_BASE - NBP_LOAD_ADDR) as usize;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const BROADCAST_MAC: [u8; 6] = [0xFF; 6];
+
+const DHCP_SERVER_PORT: u16 = 67;
+const DHCP_CLIENT_PORT: u16 = 68;
+const DHCP_MAGIC: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+const PXE_CLASS_ID: &[u8] = b"PXEClient:Arch:00000:UNDI:002001";
+
+const TFTP_SERVER_PORT: u16 = 69;
+const TFTP_CLIENT_PORT: u16 = 2070;
+const TFTP_BLOCK_SIZE: usize = 512;
+const TFTP_OP_RRQ: u16 = 1;
+const TFTP_OP_DATA: u16 = 3;
+const TFTP_OP_ACK: u16 = 4;
+const TFTP_OP_ERROR: u16 = 5;
+
+// PXE API opcodes (PXE 2.1 specification).
+const PXENV_UNDI_SHUTDOWN: u16 = 0x0005;
+const PXENV_UNDI_GET_INFORMATION: u16 = 0x000C;
+const PXENV_STOP_UNDI: u16 = 0x0015;
+const PXENV_UNLOAD_STACK: u16 = 0x0070;
+const PXENV_GET_CACHED_INFO: u16 = 0x0071;
+const PXENV_STOP_BASE: u16 = 0x0076;
+
+const PXENV_STATUS_SUCCESS: u16 = 0x0000;
+const PXENV_STATUS_UNSUPPORTED: u16 = 0x0003;
+
+const PXENV_PACKET_TYPE_DHCP_DISCOVER: u16 = 1;
+const PXENV_PACKET_TYPE_DHCP_ACK: u16 = 2;
+const PXENV_PACKET_TYPE_CACHED_REPLY: u16 = 3;
+
+/// Private `INT 1Ah` functions used by the PXE ROM stubs (`AX=5650h` is the standard PXE
+/// installation check).
+const PXE_INSTALL_CHECK_AX: u16 = 0x5650;
+const PXENV_API_AX: u16 = 0x5651;
+const PXE_API_AX: u16 = 0x5652;
+
+/// DHCP lease used for the network boot.
+struct Lease {
+    client_ip: [u8; 4],
+    netmask: [u8; 4],
+    router: Option<[u8; 4]>,
+    tftp_server: [u8; 4],
+    boot_file: Vec<u8>,
+    /// Ethernet/IP source of the DHCPACK, used to skip ARP when it also serves TFTP.
+    dhcp_mac: [u8; 6],
+    dhcp_ip: [u8; 4],
+}
+
+struct DhcpReply {
+    msg_type: u8,
+    yiaddr: [u8; 4],
+    siaddr: [u8; 4],
+    server_id: Option<[u8; 4]>,
+    netmask: Option<[u8; 4]>,
+    router: Option<[u8; 4]>,
+    tftp_server_name: Option<Vec<u8>>,
+    boot_file: Option<Vec<u8>>,
+}
+
+struct UdpPacket<'a> {
+    src_mac: [u8; 6],
+    src_ip: [u8; 4],
+    src_port: u16,
+    dst_port: u16,
+    payload: &'a [u8],
+}
+
+/// A polled NIC plus the local addresses, answering ARP requests for our IP while waiting.
+struct PxeLink<'a> {
+    nic: &'a mut dyn PxeNic,
+    mac: [u8; 6],
+    ip: [u8; 4],
+}
+
+impl PxeLink<'_> {
+    /// Transmit `request` and poll for a frame accepted by `accept`, retransmitting on timeout.
+    fn exchange<T>(
+        &mut self,
+        request: &[u8],
+        mut accept: impl FnMut(&[u8]) -> Option<T>,
+    ) -> Option<T> {
+        for _ in 0..ATTEMPTS {
+            self.nic.transmit(request);
+            for _ in 0..POLLS_PER_ATTEMPT {
+                let Some(frame) = self.nic.receive() else {
+                    continue;
+                };
+                if let Some(value) = accept(&frame) {
+                    return Some(value);
+                }
+                self.answer_arp(&frame);
+            }
+        }
+        None
+    }
+
+    fn answer_arp(&mut self, frame: &[u8]) {
+        if self.ip == [0; 4] {
+            return;
+        }
+        if let Some((1, sender_mac, sender_ip, target_ip)) = parse_arp(frame) {
+            if target_ip == self.ip {
+                let reply = arp_frame(2, self.mac, self.ip, sender_mac, sender_ip);
+                self.nic.transmit(&reply);
+            }
+        }
+    }
+
+    fn udp(&self, dst_mac: [u8; 6], dst_ip: [u8; 4], ports: (u16, u16), payload: &[u8]) -> Vec<u8> {
+        udp_frame(
+            self.mac, dst_mac, self.ip, dst_ip, ports.0, ports.1, payload,
+        )
+    }
+}
+
+impl Bios {
+    /// Boot from the network: DHCP, TFTP download of the NBP, then PXE handoff.
+    ///
+    /// Returns the NBP entry point as a real-mode `CS:IP` pair.
+    pub(super) fn boot_from_network(
+        &mut self,
+        cpu: &mut CpuState,
+        bus: &mut dyn BiosBus,
+        nic: &mut dyn PxeNic,
+    ) -> Result<(u16, u16), &'static str> {
+        let mac = nic.mac_addr();
+        let mut link = PxeLink {
+            nic,
+            mac,
+            ip: [0; 4],
+        };
+        let xid = u32::from_be_bytes([mac[2], mac[3], mac[4], mac[5]]) ^ 0x4145_524F;
+
+        let line = format!(
+            "PXE: DHCP on {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+        );
+        self.bios_diag(bus, &line);
+
+        let discover = dhcp_packet(mac, xid, DHCPDISCOVER, None);
+        let frame = link.udp(
+            BROADCAST_MAC,
+            [0xFF; 4],
+            (DHCP_CLIENT_PORT, DHCP_SERVER_PORT),
+            &discover,
+        );
+        let offer = link
+            .exchange(&frame, |frame| {
+                parse_dhcp_reply(frame, xid)
+                    .map(|(_, reply)| reply)
+                    .filter(|reply| reply.msg_type == DHCPOFFER)
+            })
+            .ok_or("No DHCP offer received")?;
+        let server_id = offer
+            .server_id
+            .ok_or("DHCP offer has no server identifier")?;
+
+        let request = dhcp_packet(mac, xid, DHCPREQUEST, Some((offer.yiaddr, server_id)));
+        let frame = link.udp(
+            BROADCAST_MAC,
+            [0xFF; 4],
+            (DHCP_CLIENT_PORT, DHCP_SERVER_PORT),
+            &request,
+        );
+        let (ack_src, ack_packet, ack) = link
+            .exchange(&frame, |frame| {
+                let (udp, reply) = parse_dhcp_reply(frame, xid)?;
+                matches!(reply.msg_type, DHCPACK | DHCPNAK)
+                    .then(|| ((udp.src_mac, udp.src_ip), udp.payload.to_vec(), reply))
+            })
+            .ok_or("No DHCP acknowledgement received")?;
+        if ack.msg_type == DHCPNAK {
+            return Err("DHCP request was refused");
+        }
+
+        let lease = lease_from_ack(&ack, ack_src)?;
+        link.ip = lease.client_ip;
+        let line = format!(
+            "PXE: IP {}, boot file \"{}\" from {}",
+            fmt_ipv4(lease.client_ip),
+            String::from_utf8_lossy(&lease.boot_file),
+            fmt_ipv4(lease.tftp_server)
+        );
+        self.bios_diag(bus, &line);
+
+        let server_mac = resolve_mac(&mut link, &lease)?;
+        let size = tftp_download(&mut link, bus, &lease, server_mac)?;
+
+        // Publish the PXE structures and hand off.
+        write_pxe_structures(bus, &discover, &ack_packet);
+        let base_mem_kb = (PXE_DATA_BASE / 1024) as u16;
+        bus.write_u16(BDA_BASE + BDA_MEM_SIZE_KB_OFFSET, base_mem_kb);
+
+        let line = format!("PXE: loaded {size} bytes, booting");
+        self.bios_diag(bus, &line);
+
+        // Stack: far return into the ROM (INT 18h), then the `!PXE` segment:offset at SP+4.
+        let sp = NBP_LOAD_ADDR as u16 - 8;
+        let stack = u64::from(sp);
+        bus.write_u16(stack, PXE_NBP_RETURN_STUB_OFFSET);
+        bus.write_u16(stack + 2, BIOS_SEGMENT);
+        bus.write_u16(stack + 4, PXE_OFFSET);
+        bus.write_u16(stack + 6, PXE_DATA_SEGMENT);
+
+        cpu.gpr[gpr::RAX] = 0;
+        cpu.gpr[gpr::RBX] = u64::from(PXENV_OFFSET);
+        cpu.gpr[gpr::RCX] = 0;
+        cpu.gpr[gpr::RDX] = 0;
+        cpu.gpr[gpr::RSI] = 0;
+        cpu.gpr[gpr::RDI] = 0;
+        cpu.gpr[gpr::RBP] = 0;
+        cpu.gpr[gpr::RSP] = u64::from(sp);
+        set_real_mode_seg(&mut cpu.segments.ss, 0x0000);
+        set_real_mode_seg(&mut cpu.segments.ds, 0x0000);
+        set_real_mode_seg(&mut cpu.segments.es, PXE_DATA_SEGMENT);
+
+        Ok((0x0000, NBP_LOAD_ADDR as u16))
+    }
+}
+
+fn lease_from_ack(ack: &DhcpReply, src: ([u8; 6], [u8; 4])) -> Result<Lease, &'static str> {
+    let boot_file = ack
+        .boot_file
+        .clone()
+        .filter(|name| !name.is_empty())
+        .ok_or("DHCP lease has no boot file name")?;
+    let tftp_server = if ack.siaddr != [0; 4] {
+        ack.siaddr
+    } else if let Some(ip) = ack.tftp_server_name.as_deref().and_then(parse_ipv4) {
+        ip
+    } else {
+        ack.server_id.ok_or("DHCP lease has no boot server")?
+    };
+    Ok(Lease {
+        client_ip: ack.yiaddr,
+        netmask: ack.netmask.unwrap_or([255, 255, 255, 0]),
+        router: ack.router,
+        tftp_server,
+        boot_file,
+        dhcp_mac: src.0,
+        dhcp_ip: src.1,
+    })
+}
+
+/// MAC address of the next hop towards the TFTP server.
+fn resolve_mac(link: &mut PxeLink<'_>, lease: &Lease) -> Result<[u8; 6], &'static str> {
+    let on_link = (0..4).all(|i| {
+        (lease.tftp_server[i] & lease.netmask[i]) == (lease.client_ip[i] & lease.netmask[i])
+    });
+    let next_hop = match lease.router {
+        Some(router) if !on_link => router,
+        _ => lease.tftp_server,
+    };
+    if next_hop == lease.dhcp_ip {
+        return Ok(lease.dhcp_mac);
+    }
+
+    let request = arp_frame(1, link.mac, link.ip, BROADCAST_MAC, next_hop);
+    link.exchange(&request, |frame| match parse_arp(frame) {
+        Some((2, sender_mac, sender_ip, _)) if sender_ip == next_hop => Some(sender_mac),
+        _ => None,
+    })
+    .ok_or("Boot server MAC address could not be resolved")
+}
+
+/// Download the boot file to `0000:7C00`, returning its size.
+fn tftp_download(
+    link: &mut PxeLink<'_>,
+    bus: &mut dyn BiosBus,
+    lease: &Lease,
+    server_mac: [u8; 6],
+) -> Result<usize, &'static str> {
+    enum Reply {
+        Data { port: u16, data: Vec<u8> },
+        Error,
+    }
+
+    let mut rrq = TFTP_OP_RRQ.to_be_bytes().to_vec();
+    rrq.extend_from_slice(&lease.boot_file);
+    rrq.push(0);
+    rrq.extend_from_slice(b"octet\0");
+    let mut request = link.udp(
+        server_mac,
+        lease.tftp_server,
+        (TFTP_CLIENT_PORT, TFTP_SERVER_PORT),
+        &rrq,
+    );
+
+    let mut server_port = None;
+    let mut block: u16 = 1;
+    let mut size = 0usize;
+    loop {
+        let reply = link
+            .exchange(&request, |frame| {
+                let udp = parse_udp(frame)?;
+                if udp.src_ip != lease.tftp_server
+                    || udp.dst_port != TFTP_CLIENT_PORT
+                    || server_port.is_some_and(|port| port != udp.src_port)
+                    || udp.payload.len() < 4
+                {
+                    return None;
+                }
+                let op = u16::from_be_bytes([udp.payload[0], udp.payload[1]]);
+                let number = u16::from_be_bytes([udp.payload[2], udp.payload[3]]);
+                match op {
+                    TFTP_OP_DATA if number == block => Some(Reply::Data {
+                        port: udp.src_port,
+                        data: udp.payload[4..].to_vec(),
+                    }),
+                    TFTP_OP_ERROR => Some(Reply::Error),
+                    _ => None,
+                }
+            })
+            .ok_or("TFTP server did not respond")?;
+        let (port, data) = match reply {
+            Reply::Data { port, data } => (port, data),
+            Reply::Error => return Err("TFTP server returned an error"),
+        };
+        server_port = Some(port);
+
+        if size + data.len() > NBP_MAX_SIZE {
+            return Err("Boot file is too large");
+        }
+        bus.write_physical(NBP_LOAD_ADDR + size as u64, &data);
+        size += data.len();
+
+        let mut ack = TFTP_OP_ACK.to_be_bytes().to_vec();
+        ack.extend_from_slice(&block.to_be_bytes());
+        request = link.udp(
+            server_mac,
+            lease.tftp_server,
+            (TFTP_CLIENT_PORT, port),
+            &ack,
+        );
+        if data.len() < TFTP_BLOCK_SIZE {
+            link.nic.transmit(&request);
+            break;
+        }
+        block = block.wrapping_add(1);
+    }
+
+    if size == 0 {
+        return Err("Boot file is empty");
+    }
+    Ok(size)
+}
+
+fn write_pxe_structures(bus: &mut dyn BiosBus, discover: &[u8], ack: &[u8]) {
+    let base = PXE_DATA_BASE;
+    bus.write_physical(base, &[0u8; 0x1000]);
+
+    // PXENV+ (PXE 2.1, 0x2C bytes).
+    let mut pxenv = [0u8; 0x2C];
+    pxenv[0..6].copy_from_slice(b"PXENV+");
+    pxenv[6..8].copy_from_slice(&0x0201u16.to_le_bytes());
+    pxenv[8] = pxenv.len() as u8;
+    pxenv[10..12].copy_from_slice(&PXENV_ENTRY_STUB_OFFSET.to_le_bytes());
+    pxenv[12..14].copy_from_slice(&BIOS_SEGMENT.to_le_bytes());
+    // BC/UNDI data: the reserved block; code: the ROM stubs.
+    for off in [28usize, 32] {
+        pxenv[off..off + 2].copy_from_slice(&PXE_DATA_SEGMENT.to_le_bytes());
+        pxenv[off + 2..off + 4].copy_from_slice(&0x1000u16.to_le_bytes());
+    }
+    for off in [24usize, 36] {
+        pxenv[off..off + 2].copy_from_slice(&BIOS_SEGMENT.to_le_bytes());
+        pxenv[off + 2..off + 4].copy_from_slice(&0x0100u16.to_le_bytes());
+    }
+    pxenv[40..42].copy_from_slice(&PXE_OFFSET.to_le_bytes());
+    pxenv[42..44].copy_from_slice(&PXE_DATA_SEGMENT.to_le_bytes());
+    pxenv[9] = checksum(&pxenv);
+    bus.write_physical(base + u64::from(PXENV_OFFSET), &pxenv);
+
+    // !PXE (0x58 bytes) with six segment descriptors: stack, UNDI data/code/code-write, BC
+    // data/code/code-write (stack left empty).
+    let mut pxe = [0u8; 0x58];
+    pxe[0..4].copy_from_slice(b"!PXE");
+    pxe[4] = pxe.len() as u8;
+    pxe[16..18].copy_from_slice(&PXE_ENTRY_STUB_OFFSET.to_le_bytes());
+    pxe[18..20].copy_from_slice(&BIOS_SEGMENT.to_le_bytes());
+    pxe[29] = 7;
+    let data = (PXE_DATA_SEGMENT, PXE_DATA_BASE as u32, 0x1000u16);
+    let code = (BIOS_SEGMENT, (u32::from(BIOS_SEGMENT)) << 4, 0x0100u16);
+    for (i, (seg, phys, len)) in [data, code, code, data, code, code].into_iter().enumerate() {
+        let off = 40 + i * 8;
+        pxe[off..off + 2].copy_from_slice(&seg.to_le_bytes());
+        pxe[off + 2..off + 6].copy_from_slice(&phys.to_le_bytes());
+        pxe[off + 6..off + 8].copy_from_slice(&len.to_le_bytes());
+    }
+    pxe[5] = checksum(&pxe);
+    bus.write_physical(base + u64::from(PXE_OFFSET), &pxe);
+
+    let discover = &discover[..discover.len().min(usize::from(DISCOVER_LIMIT))];
+    let ack = &ack[..ack.len().min(usize::from(ACK_LIMIT))];
+    bus.write_physical(base + u64::from(DISCOVER_OFFSET), discover);
+    bus.write_physical(base + u64::from(ACK_OFFSET), ack);
+    let lens = base + u64::from(CACHED_LEN_OFFSET);
+    bus.write_u16(lens, discover.len() as u16);
+    bus.write_u16(lens + 2, ack.len() as u16);
+}
+
+/// Returns `true` when a network boot has published a valid `PXENV+` structure.
+fn pxe_structures_present(bus: &mut dyn BiosBus) -> bool {
+    let mut pxenv = [0u8; 0x2C];
+    bus.read_physical(PXE_DATA_BASE + u64::from(PXENV_OFFSET), &mut pxenv);
+    &pxenv[0..6] == b"PXENV+" && checksum(&pxenv) == 0
+}
+
+/// `INT 1Ah AH=56h`: PXE installation check and the PXE API entry points.
+pub(super) fn handle_int1a_pxe(cpu: &mut CpuState, bus: &mut dyn BiosBus) {
+    let ax = cpu.gpr[gpr::RAX] as u16;
+    if !pxe_structures_present(bus) {
+        cpu.rflags |= FLAG_CF;
+        return;
+    }
+
+    let (opcode, param_seg, param_off) = match ax {
+        PXE_INSTALL_CHECK_AX => {
+            cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | 0x564E;
+            cpu.gpr[gpr::RBX] = (cpu.gpr[gpr::RBX] & !0xFFFF) | u64::from(PXENV_OFFSET);
+            set_real_mode_seg(&mut cpu.segments.es, PXE_DATA_SEGMENT);
+            cpu.rflags &= !FLAG_CF;
+            return;
+        }
+        PXENV_API_AX => (
+            cpu.gpr[gpr::RBX] as u16,
+            cpu.segments.es.selector,
+            cpu.gpr[gpr::RDI] as u16,
+        ),
+        PXE_API_AX => {
+            // Stack: IRET frame (6 bytes), the caller's far return (4 bytes), then the `!PXE`
+            // arguments: opcode, parameter offset, parameter segment.
+            let sp_bits = cpu.stack_ptr_bits();
+            let arg = |i: u64| {
+                let sp = cpu.stack_ptr().wrapping_add(10 + 2 * i) & mask_bits(sp_bits);
+                cpu.apply_a20(cpu.segments.ss.base.wrapping_add(sp))
+            };
+            let (opcode_addr, off_addr, seg_addr) = (arg(0), arg(1), arg(2));
+            (
+                bus.read_u16(opcode_addr),
+                bus.read_u16(seg_addr),
+                bus.read_u16(off_addr),
+            )
+        }
+        _ => {
+            cpu.rflags |= FLAG_CF;
+            return;
+        }
+    };
+
+    let param = (u64::from(param_seg) << 4) + u64::from(param_off);
+    let status = pxe_api_call(bus, opcode, param);
+    bus.write_u16(param, status);
+    let exit = u64::from(status != PXENV_STATUS_SUCCESS);
+    cpu.gpr[gpr::RAX] = (cpu.gpr[gpr::RAX] & !0xFFFF) | exit;
+    if status == PXENV_STATUS_SUCCESS {
+        cpu.rflags &= !FLAG_CF;
+    } else {
+        cpu.rflags |= FLAG_CF;
+    }
+}
+
+fn pxe_api_call(bus: &mut dyn BiosBus, opcode: u16, param: u64) -> u16 {
+    match opcode {
+        // The firmware's NIC access ends with POST, so there is nothing to shut down.
+        PXENV_UNDI_SHUTDOWN | PXENV_STOP_UNDI | PXENV_UNLOAD_STACK | PXENV_STOP_BASE => {
+            PXENV_STATUS_SUCCESS
+        }
+        PXENV_GET_CACHED_INFO => {
+            // t_PXENV_GET_CACHED_INFO: Status, PacketType, BufferSize, Buffer (off:seg),
+            // BufferLimit.
+            let lens = PXE_DATA_BASE + u64::from(CACHED_LEN_OFFSET);
+            let (offset, len, limit) = match bus.read_u16(param + 2) {
+                PXENV_PACKET_TYPE_DHCP_DISCOVER => {
+                    (DISCOVER_OFFSET, bus.read_u16(lens), DISCOVER_LIMIT)
+                }
+                PXENV_PACKET_TYPE_DHCP_ACK | PXENV_PACKET_TYPE_CACHED_REPLY => {
+                    (ACK_OFFSET, bus.read_u16(lens + 2), ACK_LIMIT)
+                }
+                _ => return PXENV_STATUS_UNSUPPORTED,
+            };
+            let size = bus.read_u16(param + 4);
+            let buf_off = bus.read_u16(param + 6);
+            let buf_seg = bus.read_u16(param + 8);
+            if size == 0 || (buf_off == 0 && buf_seg == 0) {
+                bus.write_u16(param + 4, len);
+                bus.write_u16(param + 6, offset);
+                bus.write_u16(param + 8, PXE_DATA_SEGMENT);
+                bus.write_u16(param + 10, limit);
+            } else {
+                let len = len.min(size);
+                let mut packet = vec![0u8; usize::from(len)];
+                bus.read_physical(PXE_DATA_BASE + u64::from(offset), &mut packet);
+                bus.write_physical((u64::from(buf_seg) << 4) + u64::from(buf_off), &packet);
+                bus.write_u16(param + 4, len);
+            }
+            PXENV_STATUS_SUCCESS
+        }
+        PXENV_UNDI_GET_INFORMATION => {
+            // The MAC is the DHCP client hardware address in the cached DHCPACK.
+            let mut mac = [0u8; 6];
+            bus.read_physical(PXE_DATA_BASE + u64::from(ACK_OFFSET) + 28, &mut mac);
+            bus.write_u16(param + 2, 0); // BaseIo
+            bus.write_u16(param + 4, 0); // IntNumber
+            bus.write_u16(param + 6, 1500); // MaxTranUnit
+            bus.write_u16(param + 8, 1); // HwType: Ethernet
+            bus.write_u16(param + 10, 6); // HwAddrLen
+            for addr in [param + 12, param + 28] {
+                let mut node = [0u8; 16];
+                node[..6].copy_from_slice(&mac);
+                bus.write_physical(addr, &node);
+            }
+            bus.write_u16(param + 44, 0); // ROMAddress
+            bus.write_u16(param + 46, 1); // RxBufCt
+            bus.write_u16(param + 48, 1); // TxBufCt
+            PXENV_STATUS_SUCCESS
+        }
+        _ => PXENV_STATUS_UNSUPPORTED,
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |acc, b| acc.wrapping_add(*b))
+        .wrapping_neg()
+}
+
+fn fmt_ipv4(ip: [u8; 4]) -> String {
+    format!("{}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3])
+}
+
+fn parse_ipv4(text: &[u8]) -> Option<[u8; 4]> {
+    let text = std::str::from_utf8(text).ok()?.trim_end_matches('\0');
+    text.parse::<std::net::Ipv4Addr>()
+        .ok()
+        .map(|ip| ip.octets())
+}
+
+/// Build a BOOTP/DHCP client packet. `request` carries the offered address and server identifier
+/// for DHCPREQUEST.
+fn dhcp_packet(
+    mac: [u8; 6],
+    xid: u32,
+    msg_type: u8,
+    request: Option<([u8; 4], [u8; 4])>,
+) -> Vec<u8> {
+    let mut packet = vec![0u8; 240];
+    packet[0] = 1; // BOOTREQUEST
+    packet[1] = 1; // Ethernet
+    packet[2] = 6;
+    packet[4..8].copy_from_slice(&xid.to_be_bytes());
+    packet[10..12].copy_from_slice(&0x8000u16.to_be_bytes()); // broadcast replies
+    packet[28..34].copy_from_slice(&mac);
+    packet[236..240].copy_from_slice(&DHCP_MAGIC);
+
+    packet.extend_from_slice(&[53, 1, msg_type]);
+    if let Some((requested_ip, server_id)) = request {
+        packet.extend_from_slice(&[50, 4]);
+        packet.extend_from_slice(&requested_ip);
+        packet.extend_from_slice(&[54, 4]);
+        packet.extend_from_slice(&server_id);
+    }
+    packet.extend_from_slice(&[57, 2, 0x05, 0xC0]); // max message size: 1472
+    packet.extend_from_slice(&[93, 2, 0x00, 0x00]); // client arch: x86 BIOS
+    packet.extend_from_slice(&[94, 3, 1, 2, 1]); // UNDI 2.1
+    packet.extend_from_slice(&[60, PXE_CLASS_ID.len() as u8]);
+    packet.extend_from_slice(PXE_CLASS_ID);
+    packet.extend_from_slice(&[55, 8, 1, 3, 6, 43, 54, 60, 66, 67]);
+    packet.push(255);
+    if packet.len() < 300 {
+        packet.resize(300, 0);
+    }
+    packet
+}
+
+fn parse_dhcp_reply(frame: &[u8], xid: u32) -> Option<(UdpPacket<'_>, DhcpReply)> {
+    let udp = parse_udp(frame)?;
+    if udp.src_port != DHCP_SERVER_PORT || udp.dst_port != DHCP_CLIENT_PORT {
+        return None;
+    }
+    let p = udp.payload;
+    if p.len() < 240 || p[0] != 2 || p[4..8] != xid.to_be_bytes() || p[236..240] != DHCP_MAGIC {
+        return None;
+    }
+    let ip = |off: usize| [p[off], p[off + 1], p[off + 2], p[off + 3]];
+    let c_string = |field: &[u8]| {
+        let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        (end > 0).then(|| field[..end].to_vec())
+    };
+
+    let mut reply = DhcpReply {
+        msg_type: 0,
+        yiaddr: ip(16),
+        siaddr: ip(20),
+        server_id: None,
+        netmask: None,
+        router: None,
+        tftp_server_name: c_string(&p[44..108]),
+        boot_file: c_string(&p[108..236]),
+    };
+    let mut opts = &p[240..];
+    while let Some((&code, rest)) = opts.split_first() {
+        match code {
+            0 => {
+                opts = rest;
+                continue;
+            }
+            255 => break,
+            _ => {}
+        }
+        let (&len, rest) = rest.split_first()?;
+        let value = rest.get(..usize::from(len))?;
+        opts = &rest[usize::from(len)..];
+        let as_ip = || (value.len() >= 4).then(|| [value[0], value[1], value[2], value[3]]);
+        match code {
+            1 => reply.netmask = as_ip(),
+            3 => reply.router = as_ip(),
+            53 => reply.msg_type = value.first().copied().unwrap_or(0),
+            54 => reply.server_id = as_ip(),
+            66 => reply.tftp_server_name = c_string(value),
+            67 => reply.boot_file = c_string(value),
+            _ => {}
+        }
+    }
+    (reply.msg_type != 0).then_some((udp, reply))
+}
+
+fn ethernet_header(dst: [u8; 6], src: [u8; 6], ethertype: u16) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(64);
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame
+}
+
+fn udp_frame(
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    src_ip: [u8; 4],
+    dst_ip: [u8; 4],
+    src_port: u16,
+    dst_port: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let udp_len = 8 + payload.len();
+    let total_len = 20 + udp_len;
+
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&(total_len as u16).to_be_bytes());
+    ip[8] = 64; // TTL
+    ip[9] = 17; // UDP
+    ip[12..16].copy_from_slice(&src_ip);
+    ip[16..20].copy_from_slice(&dst_ip);
+    let sum = ip.chunks(2).fold(0u32, |acc, w| {
+        acc + u32::from(u16::from_be_bytes([w[0], w[1]]))
+    });
+    let sum = (sum & 0xFFFF) + (sum >> 16);
+    let sum = (sum & 0xFFFF) + (sum >> 16);
+    ip[10..12].copy_from_slice(&(!(sum as u16)).to_be_bytes());
+
+    let mut frame = ethernet_header(dst_mac, src_mac, ETHERTYPE_IPV4);
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(&src_port.to_be_bytes());
+    frame.extend_from_slice(&dst_port.to_be_bytes());
+    frame.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]); // checksum optional over IPv4
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn parse_udp(frame: &[u8]) -> Option<UdpPacket<'_>> {
+    if frame.len() < 14 + 20 + 8 || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_IPV4 {
+        return None;
+    }
+    let ip = &frame[14..];
+    let ihl = usize::from(ip[0] & 0x0F) * 4;
+    let total_len = usize::from(u16::from_be_bytes([ip[2], ip[3]]));
+    let fragmented = u16::from_be_bytes([ip[6], ip[7]]) & 0x3FFF != 0;
+    if ip[0] >> 4 != 4 || ihl < 20 || ip[9] != 17 || fragmented || total_len > ip.len() {
+        return None;
+    }
+    let udp = ip.get(ihl..total_len).filter(|udp| udp.len() >= 8)?;
+    let udp_len = usize::from(u16::from_be_bytes([udp[4], udp[5]]));
+    if udp_len < 8 || udp_len > udp.len() {
+        return None;
+    }
+    Some(UdpPacket {
+        src_mac: frame[6..12].try_into().ok()?,
+        src_ip: ip[12..16].try_into().ok()?,
+        src_port: u16::from_be_bytes([udp[0], udp[1]]),
+        dst_port: u16::from_be_bytes([udp[2], udp[3]]),
+        payload: &udp[8..udp_len],
+    })
+}
+
+fn arp_frame(
+    op: u16,
+    sender_mac: [u8; 6],
+    sender_ip: [u8; 4],
+    target_mac: [u8; 6],
+    target_ip: [u8; 4],
+) -> Vec<u8> {
+    let mut frame = ethernet_header(target_mac, sender_mac, ETHERTYPE_ARP);
+    frame.extend_from_slice(&[0x00, 0x01, 0x08, 0x00, 6, 4]);
+    frame.extend_from_slice(&op.to_be_bytes());
+    frame.extend_from_slice(&sender_mac);
+    frame.extend_from_slice(&sender_ip);
+    let target_hw = if op == 1 { [0; 6] } else { target_mac };
+    frame.extend_from_slice(&target_hw);
+    frame.extend_from_slice(&target_ip);
+    frame
+}
+
+/// `(op, sender_mac, sender_ip, target_ip)` of an Ethernet/IPv4 ARP frame.
+type ArpFields = (u16, [u8; 6], [u8; 4], [u8; 4]);
+
+fn parse_arp(frame: &[u8]) -> Option<ArpFields> {
+    if frame.len() < 14 + 28 || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_ARP {
+        return None;
+    }
+    let arp = &frame[14..];
+    if arp[0..6] != [0x00, 0x01, 0x08, 0x00, 6, 4] {
+        return None;
+    }
+    Some((
+        u16::from_be_bytes([arp[6], arp[7]]),
+        arp[8..14].try_into().ok()?,
+        arp[14..18].try_into().ok()?,
+        arp[24..28].try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memory::MemoryBus as _;
+
+    #[test]
+    fn dhcp_discover_is_a_pxe_client_request() {
+        let mac = [0x52, 0x54, 0, 0x12, 0x34, 0x56];
+        let packet = dhcp_packet(mac, 0x1234_5678, DHCPDISCOVER, None);
+        assert!(packet.len() >= 300);
+        assert_eq!(packet[0], 1);
+        assert_eq!(&packet[28..34], &mac);
+        assert_eq!(&packet[236..240], &DHCP_MAGIC);
+        assert_eq!(&packet[240..243], &[53, 1, DHCPDISCOVER]);
+        assert!(packet
+            .windows(PXE_CLASS_ID.len())
+            .any(|window| window == PXE_CLASS_ID));
+    }
+
+    #[test]
+    fn udp_frame_round_trips_and_has_valid_ip_checksum() {
+        let frame = udp_frame(
+            [2; 6],
+            [3; 6],
+            [10, 0, 2, 15],
+            [10, 0, 2, 2],
+            1234,
+            69,
+            b"hello",
+        );
+        let ip = &frame[14..34];
+        let sum = ip.chunks(2).fold(0u32, |acc, w| {
+            acc + u32::from(u16::from_be_bytes([w[0], w[1]]))
+        });
+        assert_eq!((sum & 0xFFFF) + (sum >> 16), 0xFFFF);
+
+        let udp = parse_udp(&frame).unwrap();
+        assert_eq!(udp.src_mac, [2; 6]);
+        assert_eq!(udp.src_ip, [10, 0, 2, 15]);
+        assert_eq!((udp.src_port, udp.dst_port), (1234, 69));
+        assert_eq!(udp.payload, b"hello");
+    }
+
+    #[test]
+    fn arp_request_round_trips() {
+        let frame = arp_frame(1, [2; 6], [10, 0, 2, 15], BROADCAST_MAC, [10, 0, 2, 2]);
+        assert_eq!(&frame[0..6], &BROADCAST_MAC);
+        assert_eq!(
+            parse_arp(&frame),
+            Some((1, [2; 6], [10, 0, 2, 15], [10, 0, 2, 2]))
+        );
+    }
+
+    #[test]
+    fn pxe_structures_have_valid_checksums() {
+        let mut mem = crate::bios::TestMemory::new(1024 * 1024);
+        write_pxe_structures(&mut mem, &[1; 300], &[2; 400]);
+        assert!(pxe_structures_present(&mut mem));
+
+        let mut pxe = [0u8; 0x58];
+        mem.read_physical(PXE_DATA_BASE + u64::from(PXE_OFFSET), &mut pxe);
+        assert_eq!(&pxe[0..4], b"!PXE");
+        assert_eq!(checksum(&pxe), 0);
+        assert_eq!(
+            u16::from_le_bytes([pxe[16], pxe[17]]),
+            PXE_ENTRY_STUB_OFFSET
+        );
+    }
+}
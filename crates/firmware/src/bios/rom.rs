@@ -1,6 +1,7 @@
 use super::{
     BIOS_SIZE, DEFAULT_INT_STUB_OFFSET, DISKETTE_PARAM_TABLE_OFFSET, FIXED_DISK_PARAM_TABLE_OFFSET,
     INT10_STUB_OFFSET, INT13_STUB_OFFSET, INT15_STUB_OFFSET, INT16_STUB_OFFSET, INT1A_STUB_OFFSET,
+    PXENV_ENTRY_STUB_OFFSET, PXE_ENTRY_STUB_OFFSET, PXE_NBP_RETURN_STUB_OFFSET,
     VGA_FONT_8X16_OFFSET, VGA_FONT_8X8_OFFSET, VIDEO_PARAM_TABLE_OFFSET,
 };
 
@@ -45,6 +46,25 @@ pub fn build_bios_rom() -> Vec<u8> {
     write_stub(&mut rom, INT16_STUB_OFFSET, &stub);
     write_stub(&mut rom, INT1A_STUB_OFFSET, &stub);
 
+    // PXE API entry points (far-called by a network bootstrap program). They re-enter the BIOS
+    // through INT 1Ah with a private function code: `mov ax, imm16; int 1Ah; retf`.
+    write_stub(
+        &mut rom,
+        PXENV_ENTRY_STUB_OFFSET,
+        &[0xB8, 0x51, 0x56, 0xCD, 0x1A, 0xCB],
+    );
+    write_stub(
+        &mut rom,
+        PXE_ENTRY_STUB_OFFSET,
+        &[0xB8, 0x52, 0x56, 0xCD, 0x1A, 0xCB],
+    );
+    // Return target for the NBP: `int 18h; cli; hlt; jmp $-1`.
+    write_stub(
+        &mut rom,
+        PXE_NBP_RETURN_STUB_OFFSET,
+        &[0xCD, 0x18, 0xFA, 0xF4, 0xEB, 0xFD],
+    );
+
     // Diskette Parameter Table (IVT vector 0x1E).
     //
     // This is an 11-byte table traditionally used by DOS-era software to probe or patch floppy
@@ -3,10 +3,13 @@ use std::io::{Read, Write};
 use crate::bda::BiosDataArea;
 use crate::memory::MemoryBus;
 use crate::rtc::CmosRtcSnapshot;
+use crate::smbios::SmbiosIdentity;
 use crate::video::vbe::VbeDevice;
 
 use super::bda_time::BdaTimeSnapshot;
-use super::{Bios, BiosBootDevice, BiosConfig, E820Entry, ElToritoBootInfo, ElToritoBootMediaType};
+use super::{
+    Bios, BiosConfig, BootEntry, BootMenuState, E820Entry, ElToritoBootInfo, ElToritoBootMediaType,
+};
 
 #[derive(Debug, Clone)]
 pub struct VbeSnapshot {
@@ -168,6 +171,113 @@ pub struct BiosSnapshot {
     pub last_int13_status: u8,
     pub vbe: VbeSnapshot,
     pub el_torito_boot_info: Option<ElToritoBootInfoSnapshot>,
+    /// Boot-order entry the last POST booted from.
+    pub booted_entry: Option<BootEntry>,
+    /// Boot menu waiting for a selection (snapshot taken while the menu is displayed).
+    pub boot_menu: Option<BootMenuState>,
+}
+
+fn encode_boot_entry<W: Write>(w: &mut W, entry: BootEntry) -> std::io::Result<()> {
+    w.write_all(&[entry.class_code(), entry.index()])
+}
+
+fn decode_boot_entry<R: Read>(r: &mut R) -> std::io::Result<Option<BootEntry>> {
+    let mut buf = [0u8; 2];
+    r.read_exact(&mut buf)?;
+    Ok(BootEntry::from_class_code(buf[0], buf[1]))
+}
+
+/// Maximum length of a single SMBIOS identity string accepted when decoding a snapshot.
+const MAX_SMBIOS_STRING_LEN: u16 = 1024;
+
+fn encode_smbios_string<W: Write>(w: &mut W, value: Option<&str>) -> std::io::Result<()> {
+    match value {
+        Some(value) => {
+            let bytes = &value.as_bytes()[..value.len().min(usize::from(MAX_SMBIOS_STRING_LEN))];
+            w.write_all(&[1])?;
+            w.write_all(&(bytes.len() as u16).to_le_bytes())?;
+            w.write_all(bytes)
+        }
+        None => w.write_all(&[0]),
+    }
+}
+
+fn decode_smbios_string<R: Read>(r: &mut R) -> std::io::Result<Option<String>> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    if b[0] == 0 {
+        return Ok(None);
+    }
+    let mut buf2 = [0u8; 2];
+    r.read_exact(&mut buf2)?;
+    let len = u16::from_le_bytes(buf2);
+    if len > MAX_SMBIOS_STRING_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "SMBIOS string in snapshot is too long",
+        ));
+    }
+    let mut bytes = vec![0u8; usize::from(len)];
+    r.read_exact(&mut bytes)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn encode_smbios_identity<W: Write>(w: &mut W, identity: &SmbiosIdentity) -> std::io::Result<()> {
+    for value in [
+        &identity.system_manufacturer,
+        &identity.system_product_name,
+        &identity.system_version,
+        &identity.system_serial_number,
+        &identity.system_sku_number,
+        &identity.system_family,
+        &identity.baseboard_manufacturer,
+        &identity.baseboard_product,
+        &identity.baseboard_version,
+        &identity.baseboard_serial_number,
+        &identity.baseboard_asset_tag,
+    ] {
+        encode_smbios_string(w, value.as_deref())?;
+    }
+    match identity.chassis_type {
+        Some(ty) => w.write_all(&[1, ty])?,
+        None => w.write_all(&[0, 0])?,
+    }
+    let oem_len: u8 = identity.oem_strings.len().try_into().unwrap_or(u8::MAX);
+    w.write_all(&[oem_len])?;
+    for value in identity.oem_strings.iter().take(oem_len as usize) {
+        encode_smbios_string(w, Some(value))?;
+    }
+    Ok(())
+}
+
+fn decode_smbios_identity<R: Read>(r: &mut R) -> std::io::Result<SmbiosIdentity> {
+    let mut identity = SmbiosIdentity::default();
+    for value in [
+        &mut identity.system_manufacturer,
+        &mut identity.system_product_name,
+        &mut identity.system_version,
+        &mut identity.system_serial_number,
+        &mut identity.system_sku_number,
+        &mut identity.system_family,
+        &mut identity.baseboard_manufacturer,
+        &mut identity.baseboard_product,
+        &mut identity.baseboard_version,
+        &mut identity.baseboard_serial_number,
+        &mut identity.baseboard_asset_tag,
+    ] {
+        *value = decode_smbios_string(r)?;
+    }
+    let mut buf2 = [0u8; 2];
+    r.read_exact(&mut buf2)?;
+    identity.chassis_type = (buf2[0] != 0).then_some(buf2[1]);
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    for _ in 0..b[0] {
+        if let Some(value) = decode_smbios_string(r)? {
+            identity.oem_strings.push(value);
+        }
+    }
+    Ok(identity)
 }
 
 impl BiosSnapshot {
@@ -268,7 +378,7 @@ impl BiosSnapshot {
         let boot_order_len: u8 = self.config.boot_order.len().try_into().unwrap_or(u8::MAX);
         w.write_all(&[boot_order_len])?;
         for dev in self.config.boot_order.iter().take(boot_order_len as usize) {
-            w.write_all(&[*dev as u8])?;
+            w.write_all(&[dev.class_code()])?;
         }
         w.write_all(&[self.config.cd_boot_drive])?;
         w.write_all(&[self.config.boot_from_cd_if_present as u8])?;
@@ -309,6 +419,39 @@ impl BiosSnapshot {
             None => w.write_all(&[0])?,
         }
 
+        // v8 extension block: boot-order device indices, booted entry and pending boot menu.
+        //
+        // The v5 block only carries device classes; this block supersedes its boot order with
+        // full `(class, index)` entries.
+        w.write_all(&[7])?;
+        w.write_all(&[boot_order_len])?;
+        for &entry in self.config.boot_order.iter().take(boot_order_len as usize) {
+            encode_boot_entry(w, entry)?;
+        }
+        match self.booted_entry {
+            Some(entry) => {
+                w.write_all(&[1])?;
+                encode_boot_entry(w, entry)?;
+            }
+            None => w.write_all(&[0])?,
+        }
+        match &self.boot_menu {
+            Some(menu) => {
+                w.write_all(&[1])?;
+                w.write_all(&menu.remaining_ms.to_le_bytes())?;
+                let len: u8 = menu.entries.len().try_into().unwrap_or(u8::MAX);
+                w.write_all(&[len])?;
+                for &entry in menu.entries.iter().take(len as usize) {
+                    encode_boot_entry(w, entry)?;
+                }
+            }
+            None => w.write_all(&[0])?,
+        }
+
+        // v9 extension block: SMBIOS identity strings (Types 1/2/3/11).
+        w.write_all(&[8])?;
+        encode_smbios_identity(w, &self.config.smbios)?;
+
         Ok(())
     }
 
@@ -409,6 +552,8 @@ impl BiosSnapshot {
         let mut last_int13_status = 0;
         let mut vbe = VbeSnapshot::default();
         let mut el_torito_boot_info: Option<ElToritoBootInfoSnapshot> = None;
+        let mut booted_entry: Option<BootEntry> = None;
+        let mut boot_menu: Option<BootMenuState> = None;
         let mut acpi_reclaimable = None;
         let mut acpi_nvs = None;
         let mut smbios_eps_addr = None;
@@ -484,30 +629,16 @@ impl BiosSnapshot {
                         }
                     }
                     4 => {
-                        const MAX_BOOT_ORDER_LEN: u8 = 32;
-
+                        // The class-only boot order of this block predates per-device entries.
+                        // Snapshots that only carry this block recorded the old `[Hdd]` default,
+                        // which meant "boot `boot_drive`"; converting it would make POST ignore
+                        // `boot_drive` and CD-first. Skip it and keep the default (empty) order;
+                        // the v8 block restores the real one.
                         let mut b = [0u8; 1];
                         r.read_exact(&mut b)?;
-                        let encoded_len = b[0];
-
-                        let mut boot_order =
-                            Vec::with_capacity((encoded_len.min(MAX_BOOT_ORDER_LEN)) as usize);
-                        for i in 0..encoded_len {
+                        for _ in 0..b[0] {
                             r.read_exact(&mut b)?;
-                            let Some(dev) = (match b[0] {
-                                0 => Some(BiosBootDevice::Hdd),
-                                1 => Some(BiosBootDevice::Cdrom),
-                                2 => Some(BiosBootDevice::Floppy),
-                                _ => None,
-                            }) else {
-                                // Skip unknown device codes for forward compatibility.
-                                continue;
-                            };
-                            if i < MAX_BOOT_ORDER_LEN {
-                                boot_order.push(dev);
-                            }
                         }
-                        config.boot_order = boot_order;
 
                         r.read_exact(&mut b)?;
                         config.cd_boot_drive = b[0];
@@ -556,6 +687,52 @@ impl BiosSnapshot {
                             sector_count: (mask & (1 << 3) != 0).then_some(sector_count_raw),
                         });
                     }
+                    7 => {
+                        const MAX_BOOT_ORDER_LEN: u8 = 32;
+
+                        let mut b = [0u8; 1];
+                        r.read_exact(&mut b)?;
+                        let mut boot_order = Vec::new();
+                        for i in 0..b[0] {
+                            // Skip unknown device codes for forward compatibility.
+                            if let Some(entry) = decode_boot_entry(r)? {
+                                if i < MAX_BOOT_ORDER_LEN {
+                                    boot_order.push(entry);
+                                }
+                            }
+                        }
+                        config.boot_order = boot_order;
+
+                        r.read_exact(&mut b)?;
+                        booted_entry = if b[0] != 0 {
+                            decode_boot_entry(r)?
+                        } else {
+                            None
+                        };
+
+                        r.read_exact(&mut b)?;
+                        boot_menu = if b[0] != 0 {
+                            let mut buf8 = [0u8; 8];
+                            r.read_exact(&mut buf8)?;
+                            let remaining_ms = u64::from_le_bytes(buf8);
+                            r.read_exact(&mut b)?;
+                            let mut entries = Vec::new();
+                            for _ in 0..b[0] {
+                                if let Some(entry) = decode_boot_entry(r)? {
+                                    entries.push(entry);
+                                }
+                            }
+                            Some(BootMenuState {
+                                entries,
+                                remaining_ms,
+                            })
+                        } else {
+                            None
+                        };
+                    }
+                    8 => {
+                        config.smbios = decode_smbios_identity(r)?;
+                    }
                     _ => {
                         // Unknown extension; ignore trailing bytes.
                         break;
@@ -581,6 +758,8 @@ impl BiosSnapshot {
             last_int13_status,
             vbe,
             el_torito_boot_info,
+            booted_entry,
+            boot_menu,
         })
     }
 }
@@ -615,6 +794,8 @@ impl Bios {
                     load_segment: info.load_segment,
                     sector_count: info.sector_count,
                 }),
+            booted_entry: self.booted_entry,
+            boot_menu: self.boot_menu.clone(),
         }
     }
 
@@ -655,6 +836,8 @@ impl Bios {
                 sector_count: info.sector_count,
             })
         });
+        self.booted_entry = snapshot.booted_entry;
+        self.boot_menu = snapshot.boot_menu;
         snapshot.vbe.restore(&mut self.video.vbe);
         if let Some(base) = self.config.vbe_lfb_base {
             self.video.vbe.lfb_base = base;
@@ -864,12 +1047,17 @@ mod tests {
     #[test]
     fn bios_snapshot_encode_decode_preserves_boot_order_and_cd_policy_config() {
         let cfg = BiosConfig {
-            boot_order: vec![BiosBootDevice::Cdrom, BiosBootDevice::Hdd],
+            boot_order: vec![BootEntry::Cdrom(1), BootEntry::Floppy, BootEntry::Hdd(0)],
             cd_boot_drive: 0xE1,
             boot_from_cd_if_present: true,
             ..BiosConfig::default()
         };
-        let bios = Bios::new(cfg.clone());
+        let mut bios = Bios::new(cfg.clone());
+        bios.booted_entry = Some(BootEntry::Floppy);
+        bios.boot_menu = Some(BootMenuState {
+            entries: vec![BootEntry::Hdd(0), BootEntry::Cdrom(1)],
+            remaining_ms: 1234,
+        });
         let snapshot = bios.snapshot();
 
         let mut buf = Vec::new();
@@ -877,6 +1065,8 @@ mod tests {
 
         let decoded = BiosSnapshot::decode(&mut Cursor::new(&buf)).unwrap();
         assert_eq!(decoded.config.boot_order, cfg.boot_order);
+        assert_eq!(decoded.booted_entry, Some(BootEntry::Floppy));
+        assert_eq!(decoded.boot_menu, bios.boot_menu);
         assert_eq!(decoded.config.cd_boot_drive, cfg.cd_boot_drive);
         assert_eq!(
             decoded.config.boot_from_cd_if_present,
@@ -888,17 +1078,59 @@ mod tests {
         bios2.restore_snapshot(decoded, &mut mem);
         assert_eq!(
             bios2.config().boot_order,
-            vec![BiosBootDevice::Cdrom, BiosBootDevice::Hdd]
+            vec![BootEntry::Cdrom(1), BootEntry::Floppy, BootEntry::Hdd(0)]
         );
+        assert_eq!(bios2.booted_entry(), Some(BootEntry::Floppy));
+        assert!(bios2.boot_menu_active());
         assert_eq!(bios2.config().cd_boot_drive, 0xE1);
         assert!(bios2.config().boot_from_cd_if_present);
     }
 
+    #[test]
+    fn bios_snapshot_encode_decode_preserves_smbios_identity() {
+        let identity = SmbiosIdentity {
+            system_manufacturer: Some("Contoso".to_string()),
+            system_serial_number: Some("SN-1234".to_string()),
+            baseboard_asset_tag: Some("ASSET-9".to_string()),
+            chassis_type: Some(0x17),
+            oem_strings: vec!["a=1".to_string(), "b=2".to_string()],
+            ..Default::default()
+        };
+        let bios = Bios::new(BiosConfig {
+            smbios: identity.clone(),
+            ..BiosConfig::default()
+        });
+
+        let mut buf = Vec::new();
+        bios.snapshot().encode(&mut buf).unwrap();
+        let decoded = BiosSnapshot::decode(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(decoded.config.smbios, identity);
+
+        let mut bios2 = Bios::new(BiosConfig::default());
+        let mut mem = crate::memory::VecMemory::new(2 * 1024 * 1024);
+        bios2.restore_snapshot(decoded, &mut mem);
+        assert_eq!(bios2.config().smbios, identity);
+    }
+
     #[test]
     fn bios_snapshot_decode_without_boot_order_extension_applies_defaults() {
         let decoded = BiosSnapshot::decode(&mut Cursor::new(PRE_BOOT_ORDER_EXT_SNAPSHOT)).unwrap();
-        assert_eq!(decoded.config.boot_order, vec![BiosBootDevice::Hdd]);
+        assert_eq!(decoded.config.boot_order, BiosConfig::default().boot_order);
         assert_eq!(decoded.config.cd_boot_drive, 0xE0);
         assert!(!decoded.config.boot_from_cd_if_present);
+        assert_eq!(decoded.config.smbios, SmbiosIdentity::default());
+    }
+
+    #[test]
+    fn bios_snapshot_decode_ignores_class_only_boot_order() {
+        // A pre-v8 snapshot: the v5 block with the old `[Hdd]` default order, CD-first enabled.
+        let mut bytes = PRE_BOOT_ORDER_EXT_SNAPSHOT.to_vec();
+        bytes.extend_from_slice(&[4, 1, 0, 0xE1, 1]);
+
+        let decoded = BiosSnapshot::decode(&mut Cursor::new(&bytes)).unwrap();
+        assert!(decoded.config.boot_order.is_empty());
+        assert_eq!(decoded.booted_entry, None);
+        assert_eq!(decoded.config.cd_boot_drive, 0xE1);
+        assert!(decoded.config.boot_from_cd_if_present);
     }
 }
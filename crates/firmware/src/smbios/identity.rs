@@ -0,0 +1,132 @@
+/// Maximum length (in bytes) of a host-provided SMBIOS string.
+///
+/// Longer values are truncated at a UTF-8 character boundary.
+pub const SMBIOS_MAX_STRING_LEN: usize = 64;
+
+/// Maximum number of OEM strings published in the Type 11 structure.
+pub const SMBIOS_MAX_OEM_STRINGS: usize = 32;
+
+/// SMBIOS Type 3 chassis type used when none is configured ("Desktop").
+pub const SMBIOS_DEFAULT_CHASSIS_TYPE: u8 = 0x03;
+
+/// Host-configurable SMBIOS identity strings (Types 1, 2, 3 and 11).
+///
+/// Guests (notably Windows licensing/activation) read the system manufacturer, product and serial
+/// number from SMBIOS. Unset fields fall back to the built-in Aero defaults; see
+/// [`SmbiosIdentity::effective`] for the values actually published.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmbiosIdentity {
+    /// Type 1 "Manufacturer" (default `Aero`).
+    pub system_manufacturer: Option<String>,
+    /// Type 1 "Product Name" (default `Aero VM`).
+    pub system_product_name: Option<String>,
+    /// Type 1 "Version" (default `1.0`).
+    pub system_version: Option<String>,
+    /// Type 1 "Serial Number" (default `00000000`).
+    pub system_serial_number: Option<String>,
+    /// Type 1 "SKU Number" (default: none).
+    pub system_sku_number: Option<String>,
+    /// Type 1 "Family" (default: none).
+    pub system_family: Option<String>,
+    /// Type 2 "Manufacturer" (default `Aero`).
+    pub baseboard_manufacturer: Option<String>,
+    /// Type 2 "Product" (default `Aero Baseboard`).
+    pub baseboard_product: Option<String>,
+    /// Type 2 "Version" (default `1.0`).
+    pub baseboard_version: Option<String>,
+    /// Type 2 "Serial Number" (default `00000000`).
+    pub baseboard_serial_number: Option<String>,
+    /// Type 2 "Asset Tag" (default: none).
+    pub baseboard_asset_tag: Option<String>,
+    /// Type 3 chassis type (SMBIOS spec 7.4.1, e.g. `0x03` Desktop, `0x0A` Notebook,
+    /// `0x17` Rack Mount Chassis). Defaults to [`SMBIOS_DEFAULT_CHASSIS_TYPE`].
+    pub chassis_type: Option<u8>,
+    /// Type 11 OEM strings. The structure is only published when at least one string remains after
+    /// sanitization.
+    pub oem_strings: Vec<String>,
+}
+
+impl SmbiosIdentity {
+    /// Values published in the SMBIOS tables: sanitized, with defaults filled in.
+    ///
+    /// Strings have NUL bytes removed and are capped at [`SMBIOS_MAX_STRING_LEN`] bytes; a string
+    /// that ends up empty counts as unset (SMBIOS strings cannot be empty). Empty OEM strings are
+    /// dropped and at most [`SMBIOS_MAX_OEM_STRINGS`] are kept. The chassis type is limited to
+    /// its 7-bit value field. Every field of the returned identity is `Some`, except the optional
+    /// strings without a default (SKU number, family, baseboard asset tag) when not configured.
+    pub fn effective(&self) -> SmbiosIdentity {
+        let string = |value: &Option<String>, default: &str| {
+            Some(
+                value
+                    .as_deref()
+                    .and_then(sanitize_string)
+                    .unwrap_or_else(|| default.to_string()),
+            )
+        };
+        let optional = |value: &Option<String>| value.as_deref().and_then(sanitize_string);
+
+        SmbiosIdentity {
+            system_manufacturer: string(&self.system_manufacturer, "Aero"),
+            system_product_name: string(&self.system_product_name, "Aero VM"),
+            system_version: string(&self.system_version, "1.0"),
+            system_serial_number: string(&self.system_serial_number, "00000000"),
+            system_sku_number: optional(&self.system_sku_number),
+            system_family: optional(&self.system_family),
+            baseboard_manufacturer: string(&self.baseboard_manufacturer, "Aero"),
+            baseboard_product: string(&self.baseboard_product, "Aero Baseboard"),
+            baseboard_version: string(&self.baseboard_version, "1.0"),
+            baseboard_serial_number: string(&self.baseboard_serial_number, "00000000"),
+            baseboard_asset_tag: optional(&self.baseboard_asset_tag),
+            chassis_type: Some(
+                self.chassis_type
+                    .map(|ty| ty & 0x7F)
+                    .filter(|&ty| ty != 0)
+                    .unwrap_or(SMBIOS_DEFAULT_CHASSIS_TYPE),
+            ),
+            oem_strings: self
+                .oem_strings
+                .iter()
+                .filter_map(|s| sanitize_string(s))
+                .take(SMBIOS_MAX_OEM_STRINGS)
+                .collect(),
+        }
+    }
+}
+
+fn sanitize_string(value: &str) -> Option<String> {
+    let mut out = String::with_capacity(value.len().min(SMBIOS_MAX_STRING_LEN));
+    for ch in value.chars().filter(|&ch| ch != '\0') {
+        if out.len() + ch.len_utf8() > SMBIOS_MAX_STRING_LEN {
+            break;
+        }
+        out.push(ch);
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effective_fills_defaults_and_sanitizes() {
+        let identity = SmbiosIdentity {
+            system_manufacturer: Some("Contoso\0 Ltd".to_string()),
+            system_serial_number: Some("\0".to_string()),
+            system_product_name: Some("é".repeat(40)),
+            chassis_type: Some(0x8A),
+            oem_strings: vec![String::new(), "key=value".to_string()],
+            ..Default::default()
+        }
+        .effective();
+
+        assert_eq!(identity.system_manufacturer.as_deref(), Some("Contoso Ltd"));
+        assert_eq!(identity.system_serial_number.as_deref(), Some("00000000"));
+        assert_eq!(identity.system_product_name.unwrap(), "é".repeat(32));
+        assert_eq!(identity.system_version.as_deref(), Some("1.0"));
+        assert_eq!(identity.system_family, None);
+        assert_eq!(identity.baseboard_asset_tag, None);
+        assert_eq!(identity.chassis_type, Some(0x0A));
+        assert_eq!(identity.oem_strings, vec!["key=value".to_string()]);
+    }
+}
@@ -3,14 +3,18 @@
 //! Windows (and many other PC OSes) expect SMBIOS to exist on BIOS-style
 //! platforms for hardware inventory. This module generates a clean-room SMBIOS
 //! 2.x entry point structure (EPS) plus a minimal structure table covering
-//! BIOS/system/board/chassis/cpu/memory.
+//! BIOS/system/board/chassis/OEM strings/cpu/memory.
 
 mod builder;
+mod identity;
 mod scan;
 mod structures;
 
 use crate::memory::MemoryBus;
 
+pub use identity::{
+    SmbiosIdentity, SMBIOS_DEFAULT_CHASSIS_TYPE, SMBIOS_MAX_OEM_STRINGS, SMBIOS_MAX_STRING_LEN,
+};
 pub use scan::{
     find_eps, parse_eps_table_info, parse_structure_headers, parse_structure_types,
     parse_structures, validate_eps_checksum, EpsTableInfo, SmbiosStructure, SmbiosStructureHeader,
@@ -28,6 +32,9 @@ pub struct SmbiosConfig {
     /// Deterministic seed used to generate the SMBIOS Type 1 UUID.
     pub uuid_seed: u64,
 
+    /// System/baseboard/chassis identity strings and OEM strings (Types 1, 2, 3, 11).
+    pub identity: SmbiosIdentity,
+
     /// Optional physical address to place the SMBIOS entry point structure at.
     /// If `None`, the builder will attempt to place it in the EBDA and fall
     /// back to the conventional scan region (0xF0000-0xFFFFF).
@@ -43,6 +50,7 @@ impl Default for SmbiosConfig {
             ram_bytes: 512 * 1024 * 1024,
             cpu_count: 1,
             uuid_seed: 0,
+            identity: SmbiosIdentity::default(),
             eps_addr: None,
             table_addr: None,
         }
@@ -126,6 +134,7 @@ mod tests {
             ram_bytes: 768 * 1024 * 1024,
             cpu_count: 1,
             uuid_seed: 0,
+            identity: SmbiosIdentity::default(),
             eps_addr: None,
             table_addr: None,
         };
@@ -154,6 +163,7 @@ mod tests {
             ram_bytes: 768 * 1024 * 1024,
             cpu_count: 1,
             uuid_seed: 1234,
+            identity: SmbiosIdentity::default(),
             eps_addr: None,
             table_addr: None,
         };
@@ -200,4 +210,104 @@ mod tests {
         assert_eq!(u64::from(start_kb), 0);
         assert_eq!(u64::from(end_kb) + 1, config.ram_bytes / 1024);
     }
+
+    fn structure_string(s: &SmbiosStructure<'_>, index: u8) -> Option<String> {
+        if index == 0 {
+            return None;
+        }
+        s.strings
+            .split(|&b| b == 0)
+            .nth(usize::from(index) - 1)
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    #[test]
+    fn identity_strings_are_published() {
+        let mut mem = VecMemory::new(2 * 1024 * 1024);
+        write_bda_ebda_segment(&mut mem, 0x9FC0);
+
+        let config = SmbiosConfig {
+            identity: SmbiosIdentity {
+                system_manufacturer: Some("Contoso".to_string()),
+                system_product_name: Some("Widget 3000".to_string()),
+                system_serial_number: Some("SN-42".to_string()),
+                system_family: Some("Widgets".to_string()),
+                baseboard_product: Some("WB-1".to_string()),
+                baseboard_asset_tag: Some("ASSET-7".to_string()),
+                chassis_type: Some(0x17),
+                oem_strings: vec!["alpha".to_string(), "beta".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let eps_addr = SmbiosTables::build_and_write(&config, &mut mem);
+
+        let mut eps = [0u8; builder::EPS_LENGTH as usize];
+        mem.read_physical(eps_addr as u64, &mut eps);
+        assert!(validate_eps_checksum(&eps));
+
+        let table = read_smbios_table(&mut mem, eps_addr);
+        let structures = parse_structures(&table);
+        let find = |ty: u8| {
+            *structures
+                .iter()
+                .find(|s| s.header.ty == ty)
+                .unwrap_or_else(|| panic!("type {ty} missing"))
+        };
+
+        let type1 = find(1);
+        assert_eq!(
+            structure_string(&type1, type1.formatted[4]).as_deref(),
+            Some("Contoso")
+        );
+        assert_eq!(
+            structure_string(&type1, type1.formatted[5]).as_deref(),
+            Some("Widget 3000")
+        );
+        assert_eq!(
+            structure_string(&type1, type1.formatted[6]).as_deref(),
+            Some("1.0")
+        );
+        assert_eq!(
+            structure_string(&type1, type1.formatted[7]).as_deref(),
+            Some("SN-42")
+        );
+        assert_eq!(structure_string(&type1, type1.formatted[0x19]), None);
+        assert_eq!(
+            structure_string(&type1, type1.formatted[0x1A]).as_deref(),
+            Some("Widgets")
+        );
+
+        let type2 = find(2);
+        assert_eq!(
+            structure_string(&type2, type2.formatted[4]).as_deref(),
+            Some("Aero")
+        );
+        assert_eq!(
+            structure_string(&type2, type2.formatted[5]).as_deref(),
+            Some("WB-1")
+        );
+        assert_eq!(
+            structure_string(&type2, type2.formatted[8]).as_deref(),
+            Some("ASSET-7")
+        );
+
+        let type3 = find(3);
+        assert_eq!(type3.formatted[5], 0x17);
+
+        let type11 = find(11);
+        assert_eq!(type11.formatted[4], 2);
+        assert_eq!(structure_string(&type11, 1).as_deref(), Some("alpha"));
+        assert_eq!(structure_string(&type11, 2).as_deref(), Some("beta"));
+    }
+
+    #[test]
+    fn oem_strings_structure_is_omitted_when_empty() {
+        let mut mem = VecMemory::new(2 * 1024 * 1024);
+        write_bda_ebda_segment(&mut mem, 0x9FC0);
+
+        let eps_addr = SmbiosTables::build_and_write(&SmbiosConfig::default(), &mut mem);
+        let table = read_smbios_table(&mut mem, eps_addr);
+        assert!(!parse_structure_types(&table).contains(&11));
+    }
 }
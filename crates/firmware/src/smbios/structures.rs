@@ -1,19 +1,22 @@
 use super::builder::TableBuilder;
-use super::SmbiosConfig;
+use super::{SmbiosConfig, SmbiosIdentity};
 
 pub fn push_all(config: &SmbiosConfig, builder: &mut TableBuilder) {
     let handles = Handles::new(config.cpu_count);
+    let identity = config.identity.effective();
 
     push_type0_bios_information(builder, handles.type0);
-    push_type1_system_information(config, builder, handles.type1);
-    push_type2_baseboard_information(builder, handles.type2, handles.type3);
-    push_type3_chassis_information(builder, handles.type3);
+    push_type1_system_information(config, &identity, builder, handles.type1);
+    push_type2_baseboard_information(&identity, builder, handles.type2, handles.type3);
+    push_type3_chassis_information(&identity, builder, handles.type3);
 
     for cpu_index in 0..config.cpu_count.max(1) {
         let handle = handles.type4_base.wrapping_add(cpu_index as u16);
         push_type4_processor_information(builder, handle, cpu_index);
     }
 
+    push_type11_oem_strings(&identity, builder, handles.type11);
+
     push_type16_physical_memory_array(config, builder, handles.type16);
     push_type17_memory_device(config, builder, handles.type17, handles.type16);
     push_type19_memory_array_mapped_address(config, builder, handles.type19, handles.type16);
@@ -28,6 +31,7 @@ struct Handles {
     type2: u16,
     type3: u16,
     type4_base: u16,
+    type11: u16,
     type16: u16,
     type17: u16,
     type19: u16,
@@ -50,6 +54,7 @@ impl Handles {
             type2: 0x0200,
             type3: 0x0300,
             type4_base,
+            type11: next + 0x080,
             type16: next + 0x100, // keep some spacing after CPUs
             type17: next + 0x110,
             type19: next + 0x120,
@@ -78,54 +83,82 @@ fn push_type0_bios_information(builder: &mut TableBuilder, handle: u16) {
     builder.push_structure(0, handle, &formatted, &strings);
 }
 
-fn push_type1_system_information(config: &SmbiosConfig, builder: &mut TableBuilder, handle: u16) {
-    let strings = ["Aero", "Aero VM", "1.0", "00000000"];
+/// String-set of a single structure; hands out 1-based string numbers (0 = no string).
+#[derive(Default)]
+struct StringSet<'a> {
+    strings: Vec<&'a str>,
+}
+
+impl<'a> StringSet<'a> {
+    fn add(&mut self, value: Option<&'a str>) -> u8 {
+        match value {
+            Some(value) => {
+                self.strings.push(value);
+                self.strings.len() as u8
+            }
+            None => 0,
+        }
+    }
+}
 
+fn push_type1_system_information(
+    config: &SmbiosConfig,
+    identity: &SmbiosIdentity,
+    builder: &mut TableBuilder,
+    handle: u16,
+) {
+    let mut strings = StringSet::default();
     let uuid = deterministic_uuid(config);
 
     let mut formatted = Vec::with_capacity(0x1B - 4);
-    formatted.push(1); // Manufacturer
-    formatted.push(2); // Product Name
-    formatted.push(3); // Version
-    formatted.push(4); // Serial Number
+    formatted.push(strings.add(identity.system_manufacturer.as_deref())); // Manufacturer
+    formatted.push(strings.add(identity.system_product_name.as_deref())); // Product Name
+    formatted.push(strings.add(identity.system_version.as_deref())); // Version
+    formatted.push(strings.add(identity.system_serial_number.as_deref())); // Serial Number
     formatted.extend_from_slice(&uuid);
     formatted.push(0x06); // Wake-up Type: Power Switch
-    formatted.push(0); // SKU Number
-    formatted.push(0); // Family
+    formatted.push(strings.add(identity.system_sku_number.as_deref())); // SKU Number
+    formatted.push(strings.add(identity.system_family.as_deref())); // Family
 
-    builder.push_structure(1, handle, &formatted, &strings);
+    builder.push_structure(1, handle, &formatted, &strings.strings);
 }
 
-fn push_type2_baseboard_information(builder: &mut TableBuilder, handle: u16, chassis_handle: u16) {
-    let strings = [
-        "Aero",           // Manufacturer
-        "Aero Baseboard", // Product
-        "1.0",            // Version
-        "00000000",       // Serial
-        "Mainboard",      // Location in chassis
-    ];
+fn push_type2_baseboard_information(
+    identity: &SmbiosIdentity,
+    builder: &mut TableBuilder,
+    handle: u16,
+    chassis_handle: u16,
+) {
+    let mut strings = StringSet::default();
 
     let mut formatted = Vec::with_capacity(0x0F - 4);
-    formatted.push(1); // Manufacturer
-    formatted.push(2); // Product
-    formatted.push(3); // Version
-    formatted.push(4); // Serial Number
-    formatted.push(0); // Asset Tag Number
+    formatted.push(strings.add(identity.baseboard_manufacturer.as_deref())); // Manufacturer
+    formatted.push(strings.add(identity.baseboard_product.as_deref())); // Product
+    formatted.push(strings.add(identity.baseboard_version.as_deref())); // Version
+    formatted.push(strings.add(identity.baseboard_serial_number.as_deref())); // Serial Number
+    formatted.push(strings.add(identity.baseboard_asset_tag.as_deref())); // Asset Tag Number
     formatted.push(0); // Feature Flags
-    formatted.push(5); // Location in Chassis
+    formatted.push(strings.add(Some("Mainboard"))); // Location in Chassis
     formatted.extend_from_slice(&chassis_handle.to_le_bytes());
     formatted.push(0x0A); // Board Type: Motherboard
     formatted.push(0); // Number of Contained Object Handles
 
-    builder.push_structure(2, handle, &formatted, &strings);
+    builder.push_structure(2, handle, &formatted, &strings.strings);
 }
 
-fn push_type3_chassis_information(builder: &mut TableBuilder, handle: u16) {
+fn push_type3_chassis_information(
+    identity: &SmbiosIdentity,
+    builder: &mut TableBuilder,
+    handle: u16,
+) {
     let strings = ["Aero", "1.0", "00000000"];
+    let chassis_type = identity
+        .chassis_type
+        .unwrap_or(super::SMBIOS_DEFAULT_CHASSIS_TYPE);
 
     let mut formatted = Vec::with_capacity(0x14 - 4);
     formatted.push(1); // Manufacturer
-    formatted.push(0x03); // Type: Desktop
+    formatted.push(chassis_type); // Type
     formatted.push(2); // Version
     formatted.push(3); // Serial Number
     formatted.push(0); // Asset Tag Number
@@ -163,6 +196,18 @@ fn push_type4_processor_information(builder: &mut TableBuilder, handle: u16, cpu
     builder.push_structure(4, handle, &formatted, &strings);
 }
 
+fn push_type11_oem_strings(identity: &SmbiosIdentity, builder: &mut TableBuilder, handle: u16) {
+    if identity.oem_strings.is_empty() {
+        return;
+    }
+    let strings: Vec<&str> = identity.oem_strings.iter().map(String::as_str).collect();
+
+    // Count (the strings themselves live in the string-set).
+    let formatted = [strings.len() as u8];
+
+    builder.push_structure(11, handle, &formatted, &strings);
+}
+
 fn push_type16_physical_memory_array(
     config: &SmbiosConfig,
     builder: &mut TableBuilder,
@@ -2,8 +2,8 @@ use std::sync::Arc;
 
 use aero_cpu_core::state::{gpr, CpuMode, CpuState};
 use firmware::bios::{
-    A20Gate, Bios, BiosBus, BiosConfig, FirmwareMemory, InMemoryCdrom, InMemoryDisk, BDA_BASE,
-    BIOS_SECTOR_SIZE,
+    A20Gate, Bios, BiosBus, BiosConfig, BootEntry, FirmwareMemory, InMemoryCdrom, InMemoryDisk,
+    BDA_BASE, BIOS_SECTOR_SIZE,
 };
 use memory::{DenseMemory, MapError, MemoryBus, PhysicalMemoryBus};
 
@@ -462,6 +462,147 @@ fn bios_post_cd_first_policy_falls_back_to_hdd_when_cd_is_unbootable() {
     assert!(bios.config().boot_from_cd_if_present);
 }
 
+#[test]
+fn bios_post_boot_order_falls_through_unbootable_hdd_and_missing_floppy_to_cd() {
+    let iso = TestIso::build();
+    let mut cdrom = InMemoryCdrom::new(iso.bytes.clone());
+    // No 0x55AA signature: not bootable.
+    let mut hdd = InMemoryDisk::from_boot_sector([0u8; BIOS_SECTOR_SIZE]);
+
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_order: vec![BootEntry::Hdd(0), BootEntry::Floppy, BootEntry::Cdrom(0)],
+        enable_acpi: false,
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+
+    bios.post_with_devices(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        Some(&mut cdrom),
+        None,
+        None,
+    );
+
+    assert!(!cpu.halted);
+    assert_eq!(bios.booted_entry(), Some(BootEntry::Cdrom(0)));
+    assert!(bios.booted_from_cdrom());
+    assert_eq!(cpu.gpr[gpr::RDX] as u8, 0xE0);
+    let tty = String::from_utf8_lossy(bios.tty_output()).into_owned();
+    assert!(tty.contains("Boot from Hard disk 0 failed"), "{tty}");
+    assert!(tty.contains("Boot from Floppy failed"), "{tty}");
+
+    // The host-configured boot drive is untouched by the walk.
+    assert_eq!(bios.config().boot_drive, 0x80);
+}
+
+#[test]
+fn bios_boot_menu_selects_entry_from_injected_key() {
+    let iso = TestIso::build();
+    let mut cdrom = InMemoryCdrom::new(iso.bytes.clone());
+    let mut hdd_sector = [0u8; BIOS_SECTOR_SIZE];
+    hdd_sector[0..8].copy_from_slice(b"AEROHDD!");
+    hdd_sector[510] = 0x55;
+    hdd_sector[511] = 0xAA;
+    let mut hdd = InMemoryDisk::from_boot_sector(hdd_sector);
+
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_menu: true,
+        boot_menu_timeout_ms: 100,
+        enable_acpi: false,
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+
+    bios.post_with_devices(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        Some(&mut cdrom),
+        None,
+        None,
+    );
+
+    assert!(cpu.halted);
+    assert!(bios.boot_menu_active());
+    let menu = bios.boot_menu().unwrap();
+    assert_eq!(menu.entries, vec![BootEntry::Hdd(0), BootEntry::Cdrom(0)]);
+    assert_eq!(bios.booted_entry(), None);
+    let tty = String::from_utf8_lossy(bios.tty_output()).into_owned();
+    assert!(tty.contains("  2. CD-ROM 0"), "{tty}");
+
+    // Keys outside the menu are discarded while waiting.
+    bios.push_key(0x1E61); // 'a'
+    assert_eq!(bios.pending_boot_menu_order(10, true), None);
+    assert!(bios.poll_boot_menu(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        Some(&mut cdrom),
+        None,
+        10
+    ));
+    assert_eq!(bios.boot_menu().unwrap().remaining_ms, 90);
+
+    bios.push_key(0x0332); // '2'
+    assert_eq!(
+        bios.pending_boot_menu_order(10, true),
+        Some(vec![BootEntry::Cdrom(0), BootEntry::Hdd(0)])
+    );
+    assert!(!bios.poll_boot_menu(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        Some(&mut cdrom),
+        None,
+        10
+    ));
+    assert!(!cpu.halted);
+    assert!(!bios.boot_menu_active());
+    assert_eq!(bios.booted_entry(), Some(BootEntry::Cdrom(0)));
+    assert_eq!(cpu.gpr[gpr::RDX] as u8, 0xE0);
+}
+
+#[test]
+fn bios_boot_menu_timeout_boots_configured_order() {
+    let mut hdd_sector = [0u8; BIOS_SECTOR_SIZE];
+    hdd_sector[510] = 0x55;
+    hdd_sector[511] = 0xAA;
+    let mut hdd = InMemoryDisk::from_boot_sector(hdd_sector);
+
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_menu: true,
+        boot_menu_timeout_ms: 3,
+        enable_acpi: false,
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+
+    bios.post_with_devices(&mut cpu, &mut bus, &mut hdd, None, None, None, None);
+    assert!(bios.poll_boot_menu(&mut cpu, &mut bus, &mut hdd, None, None, None, 2));
+    assert_eq!(bios.pending_boot_menu_order(0, false), None);
+    assert_eq!(
+        bios.pending_boot_menu_order(2, false),
+        Some(vec![BootEntry::Hdd(0)])
+    );
+    assert!(!bios.poll_boot_menu(&mut cpu, &mut bus, &mut hdd, None, None, None, 2));
+
+    assert!(!cpu.halted);
+    assert_eq!(bios.booted_entry(), Some(BootEntry::Hdd(0)));
+    assert_eq!(cpu.rip(), 0x7C00);
+}
+
 #[test]
 fn int13_ext_read_cd_via_dispatch_interrupt_reads_2048_sector() {
     let iso = TestIso::build();
@@ -543,3 +684,111 @@ fn int13_ext_get_drive_params_cd_reports_2048_bytes_per_sector_via_dispatch_inte
     let bytes_per_sector = bus.read_u16(table + 24);
     assert_eq!(bytes_per_sector, 2048);
 }
+
+#[test]
+fn bios_post_boots_eltorito_floppy_emulation_and_int13_reads_emulated_drive() {
+    const FLOPPY_SECTORS: usize = 2880;
+
+    // Patch the test ISO's default entry to 1.44MB floppy emulation and place a full diskette
+    // image at the boot image LBA.
+    let iso = TestIso::build();
+    let mut bytes = iso.bytes.clone();
+    let entry_off = lba_offset(iso.boot_catalog_lba) + 32;
+    bytes[entry_off + 1] = 0x02; // 1.44MB floppy emulation
+    write_u16_le(&mut bytes, entry_off + 6, 1); // load just the boot sector
+    let image_off = lba_offset(iso.boot_image_lba);
+    bytes.resize(image_off + FLOPPY_SECTORS * BIOS_SECTOR_SIZE, 0);
+    for (lba, sector) in bytes[image_off..]
+        .chunks_exact_mut(BIOS_SECTOR_SIZE)
+        .enumerate()
+    {
+        sector.fill((lba % 251) as u8);
+    }
+    bytes[image_off..image_off + 8].copy_from_slice(b"AEROFDD!");
+    bytes[image_off + 510] = 0x55;
+    bytes[image_off + 511] = 0xAA;
+    let boot_sector = bytes[image_off..image_off + BIOS_SECTOR_SIZE].to_vec();
+    let mut cdrom = InMemoryCdrom::new(bytes);
+    let mut hdd = InMemoryDisk::from_boot_sector([0u8; BIOS_SECTOR_SIZE]);
+
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_drive: 0xE0,
+        enable_acpi: false,
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+
+    bios.post_with_cdrom(&mut cpu, &mut bus, &mut hdd, &mut cdrom);
+
+    // The boot sector runs from the emulated A: drive.
+    assert!(!cpu.halted);
+    assert_eq!(cpu.gpr[gpr::RDX] as u8, 0x00);
+    assert_eq!(cpu.segments.cs.selector, 0x07C0);
+    assert_eq!(cpu.rip(), 0);
+    assert_eq!(bus.read_bytes(0x7C00, BIOS_SECTOR_SIZE), boot_sector);
+    assert!(bios.booted_from_cdrom());
+    // Drive A: is advertised in the equipment word.
+    assert_eq!(bus.read_u16(BDA_BASE + 0x10) & 0x0001, 0x0001);
+
+    // INT 13h AH=02: read C=0 H=1 S=1 (emulated LBA 18) from drive 0x00 into 0000:1000.
+    cpu.gpr[gpr::RAX] = 0x0201;
+    cpu.gpr[gpr::RCX] = 0x0001;
+    cpu.gpr[gpr::RDX] = 0x0100;
+    cpu.gpr[gpr::RBX] = 0x1000;
+    cpu.segments.es.selector = 0;
+    cpu.segments.es.base = 0;
+    install_interrupt_frame(&mut bus, &mut cpu, 0x0000, 0x0100);
+    bios.dispatch_interrupt(0x13, &mut cpu, &mut bus, &mut hdd, Some(&mut cdrom));
+    assert_eq!(bus.read_u16(0x0104) & 0x0001, 0, "CF should be clear");
+    assert_eq!(
+        bus.read_bytes(0x1000, BIOS_SECTOR_SIZE),
+        vec![18u8; BIOS_SECTOR_SIZE]
+    );
+
+    // INT 13h AH=08 reports 1.44MB geometry for the emulated drive.
+    cpu.gpr[gpr::RAX] = 0x0800;
+    cpu.gpr[gpr::RDX] = 0x0000;
+    install_interrupt_frame(&mut bus, &mut cpu, 0x0000, 0x0100);
+    bios.dispatch_interrupt(0x13, &mut cpu, &mut bus, &mut hdd, Some(&mut cdrom));
+    assert_eq!(cpu.gpr[gpr::RCX] as u16, 0x4F12); // CH=79, CL=18
+    assert_eq!((cpu.gpr[gpr::RDX] >> 8) as u8, 1); // DH=max head
+    assert_eq!(cpu.gpr[gpr::RBX] as u8, 0x04); // BL=1.44MB drive type
+
+    // INT 13h AH=4B01: specification packet describes the emulation.
+    let packet = 0x0600u64;
+    bus.write_u8(packet, 0x13);
+    cpu.gpr[gpr::RAX] = 0x4B01;
+    cpu.gpr[gpr::RDX] = 0x0000;
+    cpu.gpr[gpr::RDI] = packet;
+    // AH=08 returned ES:DI -> diskette parameter table; point ES back at the packet segment.
+    cpu.segments.es.selector = 0;
+    cpu.segments.es.base = 0;
+    install_interrupt_frame(&mut bus, &mut cpu, 0x0000, 0x0100);
+    bios.dispatch_interrupt(0x13, &mut cpu, &mut bus, &mut hdd, Some(&mut cdrom));
+    assert_eq!(bus.read_u16(0x0104) & 0x0001, 0, "CF should be clear");
+    assert_eq!(bus.read_u8(packet + 1), 0x02); // media type: 1.44MB floppy emulation
+    assert_eq!(bus.read_u8(packet + 2), 0x00); // emulated drive number
+    assert_eq!(bus.read_u32(packet + 4), iso.boot_image_lba);
+    assert_eq!(bus.read_u32(packet + 8), iso.boot_catalog_lba);
+    assert_eq!(bus.read_u16(packet + 12), 0x07C0);
+    assert_eq!(bus.read_u16(packet + 14), 1);
+    assert_eq!(bus.read_bytes(packet + 16, 3), vec![79, 18, 1]);
+
+    // The CD itself stays reachable at DL=0xE0.
+    let dap = 0x0500u64;
+    bus.write_u8(dap, 0x10);
+    bus.write_u8(dap + 1, 0);
+    bus.write_u16(dap + 2, 1);
+    bus.write_u16(dap + 4, 0x0000);
+    bus.write_u16(dap + 6, 0x2000);
+    bus.write_u64(dap + 8, 16);
+    cpu.gpr[gpr::RAX] = 0x4200;
+    cpu.gpr[gpr::RDX] = 0x00E0;
+    cpu.gpr[gpr::RSI] = 0x0500;
+    install_interrupt_frame(&mut bus, &mut cpu, 0x0000, 0x0100);
+    bios.dispatch_interrupt(0x13, &mut cpu, &mut bus, &mut hdd, Some(&mut cdrom));
+    assert_eq!(bus.read_u16(0x0104) & 0x0001, 0, "CF should be clear");
+    assert_eq!(&bus.read_bytes(0x20001, 5)[..], b"CD001");
+}
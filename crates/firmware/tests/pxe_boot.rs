@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use aero_cpu_core::state::{gpr, CpuMode, CpuState};
+use firmware::bios::{
+    A20Gate, Bios, BiosConfig, BootEntry, FirmwareMemory, InMemoryDisk, PxeNic, BIOS_SECTOR_SIZE,
+    BIOS_SEGMENT, PXE_ENTRY_STUB_OFFSET,
+};
+use memory::{DenseMemory, MapError, MemoryBus, PhysicalMemoryBus};
+
+const CLIENT_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const SERVER_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const SERVER_IP: [u8; 4] = [10, 0, 2, 2];
+const CLIENT_IP: [u8; 4] = [10, 0, 2, 15];
+const TFTP_SERVER_PORT: u16 = 4000;
+
+struct TestBus {
+    a20_enabled: bool,
+    inner: PhysicalMemoryBus,
+}
+
+impl TestBus {
+    fn new(size: u64) -> Self {
+        let ram = DenseMemory::new(size).expect("guest RAM allocation failed");
+        Self {
+            a20_enabled: false,
+            inner: PhysicalMemoryBus::new(Box::new(ram)),
+        }
+    }
+
+    fn translate_a20(&self, addr: u64) -> u64 {
+        if self.a20_enabled {
+            addr
+        } else {
+            addr & !(1u64 << 20)
+        }
+    }
+
+    fn read_bytes(&mut self, paddr: u64, len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; len];
+        self.read_physical(paddr, &mut out);
+        out
+    }
+}
+
+impl A20Gate for TestBus {
+    fn set_a20_enabled(&mut self, enabled: bool) {
+        self.a20_enabled = enabled;
+    }
+
+    fn a20_enabled(&self) -> bool {
+        self.a20_enabled
+    }
+}
+
+impl FirmwareMemory for TestBus {
+    fn map_rom(&mut self, base: u64, rom: Arc<[u8]>) {
+        let len = rom.len();
+        match self.inner.map_rom(base, rom) {
+            Ok(()) => {}
+            Err(MapError::Overlap) => {
+                let already_mapped = self
+                    .inner
+                    .rom_regions()
+                    .iter()
+                    .any(|r| r.start == base && r.data.len() == len);
+                if !already_mapped {
+                    panic!("unexpected ROM mapping overlap at 0x{base:016x}");
+                }
+            }
+            Err(MapError::AddressOverflow) => {
+                panic!("ROM mapping overflow at 0x{base:016x} (len=0x{len:x})");
+            }
+        }
+    }
+}
+
+impl MemoryBus for TestBus {
+    fn read_physical(&mut self, paddr: u64, buf: &mut [u8]) {
+        for (i, slot) in buf.iter_mut().enumerate() {
+            let addr = self.translate_a20(paddr.wrapping_add(i as u64));
+            *slot = self.inner.read_physical_u8(addr);
+        }
+    }
+
+    fn write_physical(&mut self, paddr: u64, buf: &[u8]) {
+        for (i, byte) in buf.iter().copied().enumerate() {
+            let addr = self.translate_a20(paddr.wrapping_add(i as u64));
+            self.inner.write_physical_u8(addr, byte);
+        }
+    }
+}
+
+/// In-process DHCP + TFTP server reachable through [`PxeNic`].
+struct FakePxeServer {
+    boot_file: Vec<u8>,
+    rx: VecDeque<Vec<u8>>,
+    /// Set to `false` to model a network without a DHCP server.
+    respond: bool,
+    requested_file: Option<Vec<u8>>,
+}
+
+impl FakePxeServer {
+    fn new(boot_file: Vec<u8>) -> Self {
+        Self {
+            boot_file,
+            rx: VecDeque::new(),
+            respond: true,
+            requested_file: None,
+        }
+    }
+
+    fn reply_udp(&mut self, src_port: u16, dst_port: u16, dst_ip: [u8; 4], payload: &[u8]) {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&CLIENT_MAC);
+        frame.extend_from_slice(&SERVER_MAC);
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        let mut ip = [0u8; 20];
+        ip[0] = 0x45;
+        ip[2..4].copy_from_slice(&((28 + payload.len()) as u16).to_be_bytes());
+        ip[8] = 64;
+        ip[9] = 17;
+        ip[12..16].copy_from_slice(&SERVER_IP);
+        ip[16..20].copy_from_slice(&dst_ip);
+        frame.extend_from_slice(&ip);
+        frame.extend_from_slice(&src_port.to_be_bytes());
+        frame.extend_from_slice(&dst_port.to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        self.rx.push_back(frame);
+    }
+
+    fn handle_dhcp(&mut self, request: &[u8]) {
+        let msg_type = request[242];
+        let reply_type = match msg_type {
+            1 => 2, // DISCOVER -> OFFER
+            3 => 5, // REQUEST -> ACK
+            _ => return,
+        };
+        let mut reply = vec![0u8; 240];
+        reply[0] = 2;
+        reply[1] = 1;
+        reply[2] = 6;
+        reply[4..8].copy_from_slice(&request[4..8]);
+        reply[16..20].copy_from_slice(&CLIENT_IP);
+        reply[20..24].copy_from_slice(&SERVER_IP);
+        reply[28..44].copy_from_slice(&request[28..44]);
+        reply[236..240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+        reply.extend_from_slice(&[53, 1, reply_type]);
+        reply.extend_from_slice(&[54, 4]);
+        reply.extend_from_slice(&SERVER_IP);
+        reply.extend_from_slice(&[1, 4, 255, 255, 255, 0]);
+        reply.extend_from_slice(&[67, 8]);
+        reply.extend_from_slice(b"ipxe.pxe");
+        reply.push(255);
+        self.reply_udp(67, 68, [255; 4], &reply);
+    }
+
+    fn send_block(&mut self, client_port: u16, block: u16) {
+        let start = usize::from(block - 1) * 512;
+        if start > self.boot_file.len() {
+            return;
+        }
+        let end = (start + 512).min(self.boot_file.len());
+        let mut data = vec![0, 3];
+        data.extend_from_slice(&block.to_be_bytes());
+        data.extend_from_slice(&self.boot_file[start..end]);
+        self.reply_udp(TFTP_SERVER_PORT, client_port, CLIENT_IP, &data);
+    }
+}
+
+impl PxeNic for FakePxeServer {
+    fn mac_addr(&self) -> [u8; 6] {
+        CLIENT_MAC
+    }
+
+    fn transmit(&mut self, frame: &[u8]) {
+        if !self.respond || frame.len() < 42 || frame[12..14] != [0x08, 0x00] || frame[23] != 17 {
+            return;
+        }
+        let udp = &frame[34..];
+        let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+        let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+        let payload = &udp[8..];
+        match dst_port {
+            67 => self.handle_dhcp(payload),
+            69 if payload[..2] == [0, 1] => {
+                let name_end = payload[2..].iter().position(|&b| b == 0).unwrap() + 2;
+                self.requested_file = Some(payload[2..name_end].to_vec());
+                self.send_block(src_port, 1);
+            }
+            TFTP_SERVER_PORT if payload[..2] == [0, 4] => {
+                let acked = u16::from_be_bytes([payload[2], payload[3]]);
+                self.send_block(src_port, acked + 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.rx.pop_front()
+    }
+}
+
+fn nbp_image() -> Vec<u8> {
+    // Two full TFTP blocks plus a partial one.
+    (0..1200u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn boot_sector(marker: u8) -> [u8; BIOS_SECTOR_SIZE] {
+    let mut sector = [0u8; BIOS_SECTOR_SIZE];
+    sector[0] = marker;
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+    sector
+}
+
+fn install_interrupt_frame(bus: &mut TestBus, cpu: &mut CpuState, sp: u16) {
+    cpu.mode = CpuMode::Real;
+    cpu.segments.ss.selector = 0;
+    cpu.segments.ss.base = 0;
+    cpu.segments.ss.limit = 0xFFFF;
+    cpu.gpr[gpr::RSP] = sp as u64;
+    bus.write_u16(sp as u64, 0);
+    bus.write_u16(sp as u64 + 2, 0);
+    bus.write_u16(sp as u64 + 4, 0x0202);
+}
+
+#[test]
+fn bios_pxe_boot_downloads_nbp_and_publishes_pxe_structures() {
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_order: vec![BootEntry::Network, BootEntry::Hdd(0)],
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+    let mut hdd = InMemoryDisk::from_boot_sector(boot_sector(0xD0));
+    let mut nic = FakePxeServer::new(nbp_image());
+
+    bios.post_with_devices(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        None,
+        Some(&mut nic),
+        None,
+    );
+
+    assert!(!cpu.halted);
+    assert_eq!(bios.booted_entry(), Some(BootEntry::Network));
+    assert_eq!(nic.requested_file.as_deref(), Some(&b"ipxe.pxe"[..]));
+    assert_eq!(cpu.segments.cs.selector, 0x0000);
+    assert_eq!(cpu.rip(), 0x7C00);
+    assert_eq!(bus.read_bytes(0x7C00, 1200), nbp_image());
+
+    // ES:BX -> PXENV+, SS:SP+4 -> !PXE.
+    let pxenv = ((cpu.segments.es.selector as u64) << 4) + (cpu.gpr[gpr::RBX] & 0xFFFF);
+    let pxenv_bytes = bus.read_bytes(pxenv, 0x2C);
+    assert_eq!(&pxenv_bytes[..6], b"PXENV+");
+    assert_eq!(pxenv_bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b)), 0);
+
+    let sp = cpu.gpr[gpr::RSP];
+    let pxe_off = bus.read_u16(sp + 4) as u64;
+    let pxe_seg = bus.read_u16(sp + 6) as u64;
+    let pxe = bus.read_bytes((pxe_seg << 4) + pxe_off, 0x58);
+    assert_eq!(&pxe[..4], b"!PXE");
+    assert_eq!(pxe.iter().fold(0u8, |a, b| a.wrapping_add(*b)), 0);
+    assert_eq!(
+        (
+            u16::from_le_bytes([pxe[16], pxe[17]]),
+            u16::from_le_bytes([pxe[18], pxe[19]])
+        ),
+        (PXE_ENTRY_STUB_OFFSET, BIOS_SEGMENT)
+    );
+
+    // Conventional memory shrinks to cover the PXE data block.
+    assert!(bus.read_u16(0x413) < 636);
+
+    // PXENV_GET_CACHED_INFO (DHCPACK) through the `!PXE` entry point: the ROM stub executes
+    // `INT 1Ah` with AX=5652h after the NBP's far call pushed opcode/offset/segment.
+    let param = 0x0600u64;
+    bus.write_physical(param, &[0u8; 12]);
+    bus.write_u16(param + 2, 2); // PacketType = DHCPACK
+    let sp: u16 = 0x7000;
+    bus.write_u16(sp as u64 + 6, 0); // far return IP
+    bus.write_u16(sp as u64 + 8, 0); // far return CS
+    bus.write_u16(sp as u64 + 10, 0x0071); // opcode
+    bus.write_u16(sp as u64 + 12, param as u16);
+    bus.write_u16(sp as u64 + 14, 0);
+    install_interrupt_frame(&mut bus, &mut cpu, sp);
+    cpu.gpr[gpr::RAX] = 0x5652;
+    bios.dispatch_interrupt(0x1A, &mut cpu, &mut bus, &mut hdd, None);
+
+    assert_eq!(cpu.gpr[gpr::RAX] as u16, 0);
+    assert_eq!(bus.read_u16(param), 0);
+    let len = bus.read_u16(param + 4) as usize;
+    let buf = ((bus.read_u16(param + 8) as u64) << 4) + bus.read_u16(param + 6) as u64;
+    let ack = bus.read_bytes(buf, len);
+    assert_eq!(ack[0], 2);
+    assert_eq!(&ack[16..20], &CLIENT_IP);
+    assert_eq!(&ack[28..34], &CLIENT_MAC);
+}
+
+#[test]
+fn bios_pxe_boot_without_dhcp_server_falls_through_to_next_entry() {
+    let mut bios = Bios::new(BiosConfig {
+        memory_size_bytes: 16 * 1024 * 1024,
+        boot_order: vec![BootEntry::Network, BootEntry::Hdd(0)],
+        ..BiosConfig::default()
+    });
+    let mut cpu = CpuState::new(CpuMode::Real);
+    let mut bus = TestBus::new(16 * 1024 * 1024);
+    let mut hdd = InMemoryDisk::from_boot_sector(boot_sector(0xD0));
+    let mut nic = FakePxeServer::new(nbp_image());
+    nic.respond = false;
+
+    bios.post_with_devices(
+        &mut cpu,
+        &mut bus,
+        &mut hdd,
+        None,
+        None,
+        Some(&mut nic),
+        None,
+    );
+
+    assert!(!cpu.halted);
+    assert_eq!(bios.booted_entry(), Some(BootEntry::Hdd(0)));
+    assert_eq!(bus.read_bytes(0x7C00, 1), vec![0xD0]);
+    let msg = b"Boot from Network (PXE) failed: No DHCP offer received";
+    assert!(bios
+        .tty_output()
+        .windows(msg.len())
+        .any(|window| window == msg));
+}
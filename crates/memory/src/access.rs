@@ -0,0 +1,183 @@
+//! Access size/alignment legalization for MMIO regions and I/O port ranges.
+//!
+//! Buses declare an [`AccessPolicy`] per region when it is registered and legalize every access
+//! through [`AccessPolicy::split`] before dispatching it, so device models registered with a
+//! policy only ever observe well-formed accesses.
+
+use std::ops::BitOr;
+
+/// Set of access sizes (1, 2, 4 and/or 8 bytes) a device model accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessSizes(u8);
+
+impl AccessSizes {
+    pub const BYTE: Self = Self(1);
+    pub const WORD: Self = Self(2);
+    pub const DWORD: Self = Self(4);
+    pub const QWORD: Self = Self(8);
+    /// Every size up to 8 bytes.
+    pub const ANY: Self = Self(1 | 2 | 4 | 8);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, size: usize) -> bool {
+        size.is_power_of_two() && size <= 8 && (self.0 & size as u8) != 0
+    }
+}
+
+impl BitOr for AccessSizes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+/// How a bus legalizes accesses before they reach the device model owning a region.
+///
+/// Alignment is always relative to the start of the region. Bytes a policy refuses to forward
+/// are rejected like an unmapped access: reads return all ones and writes are dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessPolicy {
+    /// The bus's historical handling: port I/O accesses are forwarded as issued, and MMIO
+    /// accesses are split into naturally aligned pieces of up to 8 bytes. Devices registered
+    /// this way must validate access sizes themselves.
+    #[default]
+    Passthrough,
+    /// Forward only single accesses of an allowed size at a naturally aligned offset; reject any
+    /// other access as a whole.
+    Natural(AccessSizes),
+    /// Split every access into the largest naturally aligned pieces of allowed sizes. Bytes no
+    /// allowed piece can cover are rejected.
+    Split(AccessSizes),
+    /// Reject every access.
+    Reject,
+}
+
+/// One access delivered by a legalized access (see [`AccessPolicy::split`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessPiece {
+    /// Byte position of the piece within the original access.
+    pub pos: usize,
+    pub size: usize,
+    /// Whether the piece reaches the device; rejected pieces read as all ones and drop writes.
+    pub accepted: bool,
+}
+
+impl AccessPolicy {
+    /// Legalize an access of `len` bytes starting `offset` bytes into a region.
+    ///
+    /// The pieces cover the access exactly, in ascending order. [`AccessPolicy::Passthrough`]
+    /// yields the access unchanged.
+    pub fn split(self, offset: u64, len: usize) -> AccessPieces {
+        AccessPieces {
+            policy: self,
+            offset,
+            len,
+            pos: 0,
+        }
+    }
+}
+
+/// Iterator returned by [`AccessPolicy::split`].
+#[derive(Debug, Clone)]
+pub struct AccessPieces {
+    policy: AccessPolicy,
+    offset: u64,
+    len: usize,
+    pos: usize,
+}
+
+impl Iterator for AccessPieces {
+    type Item = AccessPiece;
+
+    fn next(&mut self) -> Option<AccessPiece> {
+        let remaining = self.len.checked_sub(self.pos).filter(|&r| r != 0)?;
+        let pos = self.pos;
+        let (size, accepted) = match self.policy {
+            AccessPolicy::Passthrough => (remaining, true),
+            AccessPolicy::Reject => (remaining, false),
+            AccessPolicy::Natural(sizes) => (
+                remaining,
+                sizes.contains(remaining) && self.offset.is_multiple_of(remaining as u64),
+            ),
+            AccessPolicy::Split(sizes) => {
+                let addr = self.offset.wrapping_add(pos as u64);
+                [8usize, 4, 2, 1]
+                    .into_iter()
+                    .find(|&size| {
+                        sizes.contains(size)
+                            && size <= remaining
+                            && addr.is_multiple_of(size as u64)
+                    })
+                    .map_or((1, false), |size| (size, true))
+            }
+        };
+        self.pos += size;
+        Some(AccessPiece {
+            pos,
+            size,
+            accepted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(policy: AccessPolicy, offset: u64, len: usize) -> Vec<(usize, usize, bool)> {
+        policy
+            .split(offset, len)
+            .map(|p| (p.pos, p.size, p.accepted))
+            .collect()
+    }
+
+    #[test]
+    fn natural_forwards_only_aligned_allowed_sizes() {
+        let policy = AccessPolicy::Natural(AccessSizes::DWORD | AccessSizes::QWORD);
+        assert_eq!(pieces(policy, 0x10, 8), [(0, 8, true)]);
+        assert_eq!(pieces(policy, 0x14, 4), [(0, 4, true)]);
+        // Misaligned, too small, or odd-sized accesses are rejected as a whole.
+        assert_eq!(pieces(policy, 0x14, 8), [(0, 8, false)]);
+        assert_eq!(pieces(policy, 0x10, 2), [(0, 2, false)]);
+        assert_eq!(pieces(policy, 0x10, 3), [(0, 3, false)]);
+    }
+
+    #[test]
+    fn split_uses_largest_aligned_allowed_pieces() {
+        let policy =
+            AccessPolicy::Split(AccessSizes::BYTE | AccessSizes::WORD | AccessSizes::DWORD);
+        assert_eq!(pieces(policy, 0, 4), [(0, 4, true)]);
+        assert_eq!(
+            pieces(policy, 1, 4),
+            [(0, 1, true), (1, 2, true), (3, 1, true)]
+        );
+        assert_eq!(pieces(policy, 2, 3), [(0, 2, true), (2, 1, true)]);
+        assert_eq!(pieces(policy, 0, 8), [(0, 4, true), (4, 4, true)]);
+
+        let bytes = AccessPolicy::Split(AccessSizes::BYTE);
+        assert_eq!(
+            pieces(bytes, 0, 3),
+            [(0, 1, true), (1, 1, true), (2, 1, true)]
+        );
+    }
+
+    #[test]
+    fn split_rejects_bytes_no_allowed_piece_covers() {
+        let policy = AccessPolicy::Split(AccessSizes::WORD);
+        assert_eq!(
+            pieces(policy, 1, 4),
+            [(0, 1, false), (1, 2, true), (3, 1, false)]
+        );
+    }
+
+    #[test]
+    fn passthrough_and_reject_keep_the_access_whole() {
+        assert_eq!(pieces(AccessPolicy::Passthrough, 3, 3), [(0, 3, true)]);
+        assert_eq!(pieces(AccessPolicy::Reject, 0, 4), [(0, 4, false)]);
+        assert_eq!(pieces(AccessPolicy::Reject, 0, 0), []);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::access::AccessPolicy;
 use crate::phys::GuestMemory;
 use std::sync::Arc;
 
@@ -184,6 +185,8 @@ pub struct MmioRegion {
     pub start: u64,
     pub end: u64,
     pub handler: Box<dyn MmioHandler>,
+    /// Access legalization applied before `handler` is called.
+    pub policy: AccessPolicy,
 }
 
 impl MmioRegion {
@@ -198,6 +201,17 @@ pub enum MapError {
     Overlap,
 }
 
+/// Observer for accesses that reach no MMIO, ROM or RAM backing (see
+/// [`PhysicalMemoryBus::set_unmapped_access_hook`]).
+///
+/// Called with `(paddr, len, is_write)` once per contiguous unmapped chunk of an access.
+pub type UnmappedAccessHook = Box<dyn FnMut(u64, usize, bool)>;
+
+/// Observer for writes that land on a ROM region (see [`PhysicalMemoryBus::set_rom_write_hook`]).
+///
+/// Called with `(paddr, bytes)` once per contiguous ROM chunk of a write.
+pub type RomWriteHook = Box<dyn FnMut(u64, &[u8])>;
+
 /// Guest *physical* memory bus.
 ///
 /// Routes accesses to:
@@ -209,6 +223,8 @@ pub struct PhysicalMemoryBus {
     pub ram: Box<dyn GuestMemory>,
     rom_regions: Vec<RomRegion>,
     mmio_regions: Vec<MmioRegion>,
+    unmapped_hook: Option<UnmappedAccessHook>,
+    rom_write_hook: Option<RomWriteHook>,
 }
 
 impl PhysicalMemoryBus {
@@ -217,6 +233,30 @@ impl PhysicalMemoryBus {
             ram,
             rom_regions: Vec::new(),
             mmio_regions: Vec::new(),
+            unmapped_hook: None,
+            rom_write_hook: None,
+        }
+    }
+
+    /// Install (`Some`) or remove (`None`) the observer for unmapped accesses.
+    ///
+    /// The hook only observes: unmapped reads still return all 1s and unmapped writes are still
+    /// dropped. RAM-backend errors (e.g. holes in a [`crate::MappedGuestMemory`]) count as
+    /// unmapped.
+    pub fn set_unmapped_access_hook(&mut self, hook: Option<UnmappedAccessHook>) {
+        self.unmapped_hook = hook;
+    }
+
+    /// Install (`Some`) or remove (`None`) the observer for writes to ROM regions.
+    ///
+    /// The hook only observes: ROM contents never change.
+    pub fn set_rom_write_hook(&mut self, hook: Option<RomWriteHook>) {
+        self.rom_write_hook = hook;
+    }
+
+    fn notify_unmapped(&mut self, paddr: u64, len: usize, is_write: bool) {
+        if let Some(hook) = self.unmapped_hook.as_mut() {
+            hook(paddr, len, is_write);
         }
     }
 
@@ -259,6 +299,20 @@ impl PhysicalMemoryBus {
         start: u64,
         len: u64,
         handler: Box<dyn MmioHandler>,
+    ) -> Result<(), MapError> {
+        self.map_mmio_with_policy(start, len, AccessPolicy::Passthrough, handler)
+    }
+
+    /// Map an MMIO region whose accesses are legalized by `policy` (see [`AccessPolicy`]).
+    ///
+    /// Accesses are clipped to the region first, so the part of an access straddling the region
+    /// boundary is routed to whatever backs the neighbouring addresses.
+    pub fn map_mmio_with_policy(
+        &mut self,
+        start: u64,
+        len: u64,
+        policy: AccessPolicy,
+        handler: Box<dyn MmioHandler>,
     ) -> Result<(), MapError> {
         let end = start.checked_add(len).ok_or(MapError::AddressOverflow)?;
 
@@ -285,6 +339,7 @@ impl PhysicalMemoryBus {
                 start,
                 end,
                 handler,
+                policy,
             },
         );
         Ok(())
@@ -393,6 +448,7 @@ impl PhysicalMemoryBus {
                     .is_err()
                 {
                     dst[pos..pos + chunk_len].fill(0xFF);
+                    self.notify_unmapped(addr, chunk_len, false);
                 }
                 pos += chunk_len;
                 continue;
@@ -420,6 +476,7 @@ impl PhysicalMemoryBus {
             };
 
             dst[pos..pos + chunk_len].fill(0xFF);
+            self.notify_unmapped(addr, chunk_len, false);
             pos += chunk_len;
         }
     }
@@ -465,6 +522,9 @@ impl PhysicalMemoryBus {
                 let chunk_len = (chunk_end - addr) as usize;
 
                 // ROM is read-only: ignore writes.
+                if let Some(hook) = self.rom_write_hook.as_mut() {
+                    hook(addr, &src[pos..pos + chunk_len]);
+                }
                 pos += chunk_len;
                 continue;
             }
@@ -486,7 +546,13 @@ impl PhysicalMemoryBus {
                 let chunk_end = chunk_end.min(addr.saturating_add(rem as u64));
                 let chunk_len = (chunk_end - addr) as usize;
 
-                let _ = self.ram.write_from(addr, &src[pos..pos + chunk_len]);
+                if self
+                    .ram
+                    .write_from(addr, &src[pos..pos + chunk_len])
+                    .is_err()
+                {
+                    self.notify_unmapped(addr, chunk_len, true);
+                }
                 pos += chunk_len;
                 continue;
             }
@@ -512,11 +578,27 @@ impl PhysicalMemoryBus {
                 None => rem,
             };
 
+            self.notify_unmapped(addr, chunk_len, true);
             pos += chunk_len;
         }
     }
 
     fn read_mmio_chunk(&mut self, region_idx: usize, offset: u64, dst: &mut [u8]) {
+        let policy = self.mmio_regions[region_idx].policy;
+        if policy != AccessPolicy::Passthrough {
+            for piece in policy.split(offset, dst.len()) {
+                let dst = &mut dst[piece.pos..piece.pos + piece.size];
+                if !piece.accepted {
+                    dst.fill(0xFF);
+                    continue;
+                }
+                let addr = offset.wrapping_add(piece.pos as u64);
+                let value = self.mmio_regions[region_idx].handler.read(addr, piece.size);
+                dst.copy_from_slice(&value.to_le_bytes()[..piece.size]);
+            }
+            return;
+        }
+
         let mut pos = 0usize;
         while pos < dst.len() {
             let addr = offset.wrapping_add(pos as u64);
@@ -538,6 +620,24 @@ impl PhysicalMemoryBus {
     }
 
     fn write_mmio_chunk(&mut self, region_idx: usize, offset: u64, src: &[u8]) {
+        let policy = self.mmio_regions[region_idx].policy;
+        if policy != AccessPolicy::Passthrough {
+            for piece in policy
+                .split(offset, src.len())
+                .filter(|piece| piece.accepted)
+            {
+                let mut buf = [0u8; 8];
+                buf[..piece.size].copy_from_slice(&src[piece.pos..piece.pos + piece.size]);
+                let addr = offset.wrapping_add(piece.pos as u64);
+                self.mmio_regions[region_idx].handler.write(
+                    addr,
+                    piece.size,
+                    u64::from_le_bytes(buf),
+                );
+            }
+            return;
+        }
+
         let mut pos = 0usize;
         while pos < src.len() {
             let addr = offset.wrapping_add(pos as u64);
@@ -840,6 +940,58 @@ mod tests {
         assert_eq!(buf, [0xFF; 3]);
     }
 
+    #[test]
+    fn unmapped_access_hook_sees_only_unmapped_chunks() {
+        let ram = SharedRam::new(vec![0u8; 4]);
+        let mut bus = PhysicalMemoryBus::new(Box::new(ram));
+        bus.map_rom(8, Arc::from([0u8; 4].as_slice())).unwrap();
+
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        bus.set_unmapped_access_hook(Some(Box::new(
+            move |paddr: u64, len: usize, write: bool| {
+                sink.borrow_mut().push((paddr, len, write));
+            },
+        )));
+
+        // RAM [0, 4), hole [4, 8), ROM [8, 12), then unmapped to the end of the access.
+        let mut buf = [0u8; 14];
+        bus.read_physical(0, &mut buf);
+        bus.write_physical_u16(5, 0);
+        bus.write_physical_u8(2, 0);
+        assert_eq!(
+            *seen.borrow(),
+            [(4, 4, false), (12, 2, false), (5, 2, true)]
+        );
+
+        bus.set_unmapped_access_hook(None);
+        bus.read_physical_u8(4);
+        assert_eq!(seen.borrow().len(), 3);
+    }
+
+    #[test]
+    fn rom_write_hook_sees_only_rom_chunks() {
+        let ram = SharedRam::new(vec![0u8; 16]);
+        let mut bus = PhysicalMemoryBus::new(Box::new(ram));
+        bus.map_rom(8, Arc::from([0u8; 4].as_slice())).unwrap();
+
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        bus.set_rom_write_hook(Some(Box::new(move |paddr: u64, bytes: &[u8]| {
+            sink.borrow_mut().push((paddr, bytes.to_vec()));
+        })));
+
+        // RAM [0, 8), ROM [8, 12), RAM [12, 16).
+        bus.write_physical(6, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        bus.write_physical_u8(2, 0);
+        assert_eq!(*seen.borrow(), [(8, vec![3, 4, 5, 6])]);
+        assert_eq!(bus.read_physical_u8(8), 0);
+
+        bus.set_rom_write_hook(None);
+        bus.write_physical_u8(9, 0xFF);
+        assert_eq!(seen.borrow().len(), 1);
+    }
+
     #[test]
     fn rom_is_read_only_and_does_not_write_through_to_ram() {
         let ram = SharedRam::new((0u8..16).collect());
@@ -952,6 +1104,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn natural_policy_rejects_odd_misaligned_and_straddling_accesses() {
+        let ram = SharedRam::new((0u8..32).collect());
+        let mut bus = PhysicalMemoryBus::new(Box::new(ram));
+
+        let (mmio, mmio_state) = RecordingMmio::new((0x40u8..0x50).collect());
+        let sizes = crate::AccessSizes::DWORD | crate::AccessSizes::QWORD;
+        bus.map_mmio_with_policy(16, 16, AccessPolicy::Natural(sizes), Box::new(mmio))
+            .unwrap();
+
+        assert_eq!(bus.read_physical_u32(20), 0x4746_4544);
+        assert_eq!(bus.read_physical_u64(24), 0x4F4E_4D4C_4B4A_4948);
+        // Too small, misaligned and odd-sized accesses never reach the handler.
+        assert_eq!(bus.read_physical_u16(16), 0xFFFF);
+        assert_eq!(bus.read_physical_u32(18), 0xFFFF_FFFF);
+        let mut odd = [0u8; 3];
+        bus.read_physical(16, &mut odd);
+        assert_eq!(odd, [0xFF; 3]);
+        bus.write_physical_u16(16, 0);
+        bus.write_physical_u32(17, 0);
+
+        // Straddling the region start: the RAM half is served by RAM, the 2-byte MMIO half is
+        // rejected.
+        assert_eq!(bus.read_physical_u32(14), 0xFFFF_0F0E);
+
+        let state = mmio_state.lock().unwrap();
+        assert_eq!(state.reads, [(4, 4), (8, 8)]);
+        assert!(state.writes.is_empty());
+    }
+
+    #[test]
+    fn split_policy_issues_only_allowed_aligned_pieces() {
+        let ram = SharedRam::new((0u8..32).collect());
+        let mut bus = PhysicalMemoryBus::new(Box::new(ram));
+
+        let (mmio, mmio_state) = RecordingMmio::new((0x40u8..0x48).collect());
+        bus.map_mmio_with_policy(
+            8,
+            8,
+            AccessPolicy::Split(crate::AccessSizes::BYTE | crate::AccessSizes::WORD),
+            Box::new(mmio),
+        )
+        .unwrap();
+
+        // 3-byte read at an odd offset: byte, then aligned word.
+        let mut odd = [0u8; 3];
+        bus.read_physical(9, &mut odd);
+        assert_eq!(odd, [0x41, 0x42, 0x43]);
+
+        // 4-byte write straddling the region end: MMIO gets two pieces, RAM gets the rest.
+        bus.write_physical_u32(14, 0x1122_3344);
+        assert_eq!(bus.read_physical_u8(16), 0x22);
+
+        let state = mmio_state.lock().unwrap();
+        assert_eq!(state.reads, [(1, 1), (2, 2)]);
+        assert_eq!(state.writes, [(6, 2, 0x3344)]);
+    }
+
+    #[test]
+    fn reject_policy_floats_high_and_drops_writes() {
+        let ram = SharedRam::new(vec![0u8; 16]);
+        let mut bus = PhysicalMemoryBus::new(Box::new(ram));
+
+        let (mmio, mmio_state) = RecordingMmio::new(vec![0; 8]);
+        bus.map_mmio_with_policy(8, 8, AccessPolicy::Reject, Box::new(mmio))
+            .unwrap();
+
+        assert_eq!(bus.read_physical_u32(8), 0xFFFF_FFFF);
+        bus.write_physical_u32(8, 0);
+        let state = mmio_state.lock().unwrap();
+        assert!(state.reads.is_empty() && state.writes.is_empty());
+    }
+
     #[test]
     fn small_bus_avoids_unaligned_multi_byte_mmio_operations() {
         // The lightweight `Bus` router provides an 8-byte fast path for MMIO reads/writes. Ensure
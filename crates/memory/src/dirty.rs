@@ -4,6 +4,12 @@ use std::sync::{Arc, Mutex};
 #[derive(Debug)]
 struct DirtyBitmap {
     bits: Vec<u64>,
+    // Second bitmap fed by the same writes while sampling is enabled. It is harvested
+    // independently of `bits`, so draining one never loses pages the other still needs.
+    sampled: Option<Vec<u64>>,
+    // Pages written since the last harvest, in write order, while the write log is enabled. Unlike
+    // the bitmaps it costs O(pages written) to drain, so it suits very frequent harvests.
+    write_log: Option<Vec<u64>>,
     pages: usize,
     page_size: u64,
 }
@@ -21,6 +27,8 @@ impl DirtyBitmap {
         let words = pages.div_ceil(64);
         Self {
             bits: vec![0u64; words],
+            sampled: None,
+            write_log: None,
             pages,
             page_size: page_size_u64,
         }
@@ -43,6 +51,14 @@ impl DirtyBitmap {
             if let Some(slot) = self.bits.get_mut(word) {
                 *slot |= 1u64 << bit;
             }
+            if let Some(slot) = self.sampled.as_mut().and_then(|s| s.get_mut(word)) {
+                *slot |= 1u64 << bit;
+            }
+            if let Some(log) = self.write_log.as_mut() {
+                if log.last() != Some(&(page as u64)) {
+                    log.push(page as u64);
+                }
+            }
         }
     }
 
@@ -114,6 +130,72 @@ impl DirtyTracker {
         bitmap.clear();
     }
 
+    /// Start (or keep) recording writes into the sampling bitmap.
+    ///
+    /// The sampling bitmap is fed by the same writes as the snapshot dirty set but is drained only
+    /// by [`DirtyTracker::take_sampled_bitmap`]: [`DirtyTracker::take_dirty_pages`] and
+    /// [`DirtyTracker::clear_dirty`] never touch it, and harvesting it never touches the snapshot
+    /// dirty set.
+    pub fn enable_sampling(&self) {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return;
+        };
+        if bitmap.sampled.is_none() {
+            bitmap.sampled = Some(vec![0u64; bitmap.bits.len()]);
+        }
+    }
+
+    /// Stop recording writes into the sampling bitmap and drop its contents.
+    pub fn disable_sampling(&self) {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return;
+        };
+        bitmap.sampled = None;
+    }
+
+    /// Return and clear the sampling bitmap (one bit per page, 64 pages per word), or `None` if
+    /// sampling is disabled.
+    pub fn take_sampled_bitmap(&self) -> Option<Vec<u64>> {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return None;
+        };
+        let words = bitmap.bits.len();
+        let sampled = bitmap.sampled.as_mut()?;
+        Some(std::mem::replace(sampled, vec![0u64; words]))
+    }
+
+    /// Start (or keep) recording written pages into the write log.
+    ///
+    /// Like the sampling bitmap, the write log is independent of the snapshot dirty set.
+    pub fn enable_write_log(&self) {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return;
+        };
+        if bitmap.write_log.is_none() {
+            bitmap.write_log = Some(Vec::new());
+        }
+    }
+
+    /// Stop recording the write log and drop its contents.
+    pub fn disable_write_log(&self) {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return;
+        };
+        bitmap.write_log = None;
+    }
+
+    /// Return and clear the pages written since the previous call, or `None` if the write log is
+    /// disabled.
+    ///
+    /// Pages are listed in write order; consecutive writes to the same page are reported once, but
+    /// a page may appear again after writes to other pages.
+    pub fn take_write_log(&self) -> Option<Vec<u64>> {
+        let Ok(mut bitmap) = self.inner.lock() else {
+            return None;
+        };
+        bitmap.write_log.as_mut().map(std::mem::take)
+    }
+
     /// Mark a guest-physical byte range as dirty.
     pub fn mark_range(&self, start: u64, len: usize) {
         let Ok(mut bitmap) = self.inner.lock() else {
@@ -189,6 +271,18 @@ impl GuestMemory for DirtyGuestMemory {
         self.tracker.mark_range(paddr, len);
         Some(slice)
     }
+
+    fn discard(&mut self, paddr: u64, len: u64) -> crate::phys::GuestMemoryResult<()> {
+        self.inner.discard(paddr, len)?;
+        // Discarded pages now read as zero; snapshots must pick up the new contents.
+        self.tracker
+            .mark_range(paddr, usize::try_from(len).unwrap_or(usize::MAX));
+        Ok(())
+    }
+
+    fn resident_bytes(&self) -> u64 {
+        self.inner.resident_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -241,4 +335,53 @@ mod tests {
         mem.read_into(0x0, &mut buf).unwrap();
         assert!(tracker.take_dirty_pages().is_empty());
     }
+
+    #[test]
+    fn sampling_bitmap_is_independent_of_snapshot_dirty_set() {
+        let inner = DenseMemory::new(3 * u64::from(PAGE_SIZE)).unwrap();
+        let (mut mem, tracker) = DirtyGuestMemory::new(Box::new(inner), PAGE_SIZE);
+        assert_eq!(tracker.take_sampled_bitmap(), None);
+
+        // Writes before sampling starts are only seen by the snapshot dirty set.
+        mem.write_from(0x0, &[1]).unwrap();
+        tracker.enable_sampling();
+        mem.write_from(0x2000, &[2]).unwrap();
+
+        // Harvesting the sample leaves the snapshot dirty set intact...
+        assert_eq!(tracker.take_sampled_bitmap(), Some(vec![0b100]));
+        assert_eq!(tracker.take_sampled_bitmap(), Some(vec![0]));
+        mem.write_from(0x1000, &[3]).unwrap();
+        assert_eq!(tracker.take_dirty_pages(), vec![0, 1, 2]);
+
+        // ...and draining or clearing the snapshot dirty set leaves the sample intact.
+        mem.write_from(0x0, &[4]).unwrap();
+        tracker.clear_dirty();
+        assert_eq!(tracker.take_sampled_bitmap(), Some(vec![0b011]));
+
+        tracker.disable_sampling();
+        mem.write_from(0x0, &[5]).unwrap();
+        assert_eq!(tracker.take_sampled_bitmap(), None);
+        assert_eq!(tracker.take_dirty_pages(), vec![0]);
+    }
+
+    #[test]
+    fn write_log_lists_written_pages_in_order() {
+        let inner = DenseMemory::new(3 * u64::from(PAGE_SIZE)).unwrap();
+        let (mut mem, tracker) = DirtyGuestMemory::new(Box::new(inner), PAGE_SIZE);
+        assert_eq!(tracker.take_write_log(), None);
+
+        mem.write_from(0x0, &[1]).unwrap();
+        tracker.enable_write_log();
+        mem.write_from(0x2000, &[2]).unwrap();
+        mem.write_from(0x2001, &[3]).unwrap();
+        mem.write_from(0x0FFF, &[4, 5]).unwrap();
+        mem.write_from(0x2000, &[6]).unwrap();
+        assert_eq!(tracker.take_write_log(), Some(vec![2, 0, 1, 2]));
+        assert_eq!(tracker.take_write_log(), Some(vec![]));
+        assert_eq!(tracker.take_dirty_pages(), vec![0, 1, 2]);
+
+        tracker.disable_write_log();
+        mem.write_from(0x0, &[7]).unwrap();
+        assert_eq!(tracker.take_write_log(), None);
+    }
 }
@@ -4,6 +4,7 @@
 //! *physical* memory bus (`MemoryBus`) used by the MMU for page table walks. The [`bus`] module also
 //! contains routing implementations that support RAM/ROM/MMIO.
 
+pub mod access;
 pub mod bus;
 pub mod dirty;
 pub mod mapped;
@@ -11,7 +12,11 @@ pub mod mmu;
 pub mod phys;
 pub mod tlb;
 
-pub use bus::{Bus, MapError, MemoryBus, MmioHandler, MmioRegion, PhysicalMemoryBus, RomRegion};
+pub use access::{AccessPiece, AccessPieces, AccessPolicy, AccessSizes};
+pub use bus::{
+    Bus, MapError, MemoryBus, MmioHandler, MmioRegion, PhysicalMemoryBus, RomRegion, RomWriteHook,
+    UnmappedAccessHook,
+};
 pub use dirty::{DirtyGuestMemory, DirtyTracker};
 pub use mapped::{GuestMemoryMapping, MappedGuestMemory, MappedGuestMemoryError};
 pub use mmu::{AccessType, Mmu, TranslateError};
@@ -227,6 +227,7 @@ impl MappedGuestMemory {
     #[inline]
     fn map_addr(region: &GuestMemoryMapping, paddr: u64) -> u64 {
         // Safe due to construction-time validation.
+        debug_assert!((region.phys_start..region.phys_end).contains(&paddr));
         region.inner_offset + (paddr - region.phys_start)
     }
 }
@@ -335,6 +336,40 @@ impl GuestMemory for MappedGuestMemory {
         let inner_addr = Self::map_addr(region, paddr);
         self.inner.get_slice_mut(inner_addr, len)
     }
+
+    fn discard(&mut self, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+        let end = paddr
+            .checked_add(len)
+            .filter(|&end| end <= self.phys_size)
+            .ok_or(GuestMemoryError::OutOfRange {
+                paddr,
+                len: usize::try_from(len).unwrap_or(usize::MAX),
+                size: self.phys_size,
+            })?;
+
+        // Holes have no backing storage; only the mapped parts of the range are discarded.
+        let mut idx = self.first_region_index_for_addr(paddr);
+        while let Some(region) = self.regions.get(idx) {
+            if region.phys_start >= end {
+                break;
+            }
+
+            let inter_start = paddr.max(region.phys_start);
+            let inter_end = end.min(region.phys_end);
+            if inter_start < inter_end {
+                let inner_addr = Self::map_addr(region, inter_start);
+                self.inner.discard(inner_addr, inter_end - inter_start)?;
+            }
+
+            idx += 1;
+        }
+
+        Ok(())
+    }
+
+    fn resident_bytes(&self) -> u64 {
+        self.inner.resident_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -57,6 +57,32 @@ pub trait GuestMemory {
         None
     }
 
+    /// Discards the contents of `[paddr, paddr + len)`; the range subsequently reads as zero.
+    ///
+    /// Backends that allocate storage lazily (e.g. [`SparseMemory`]) release storage that no longer
+    /// holds any data so the host can reclaim it (e.g. for virtio-balloon). The default
+    /// implementation zero-fills the range.
+    fn discard(&mut self, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+        const ZEROES: [u8; 4096] = [0; 4096];
+
+        check_range_u64(self.size(), paddr, len)?;
+        let end = paddr + len;
+        let mut cur = paddr;
+        while cur < end {
+            let take = (end - cur).min(ZEROES.len() as u64) as usize;
+            self.write_from(cur, &ZEROES[..take])?;
+            cur += take as u64;
+        }
+        Ok(())
+    }
+
+    /// Host memory currently committed to this backend, in bytes.
+    ///
+    /// Defaults to [`GuestMemory::size`] for fully-allocated backends.
+    fn resident_bytes(&self) -> u64 {
+        self.size()
+    }
+
     fn read_u8_le(&self, paddr: u64) -> GuestMemoryResult<u8> {
         let mut buf = [0u8; 1];
         self.read_into(paddr, &mut buf)?;
@@ -119,6 +145,19 @@ fn check_range(size: u64, paddr: u64, len: usize) -> GuestMemoryResult<()> {
     Ok(())
 }
 
+fn check_range_u64(size: u64, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+    let err = || GuestMemoryError::OutOfRange {
+        paddr,
+        len: usize::try_from(len).unwrap_or(usize::MAX),
+        size,
+    };
+    let end = paddr.checked_add(len).ok_or_else(err)?;
+    if end > size {
+        return Err(err());
+    }
+    Ok(())
+}
+
 // -------------------------------------------------------------------------------------------------
 // wasm32 shared-linear-memory guest RAM backend
 // -------------------------------------------------------------------------------------------------
@@ -372,6 +411,14 @@ impl GuestMemory for DenseMemory {
         let (start, end) = self.range_to_usize(paddr, len).ok()?;
         Some(&mut self.data[start..end])
     }
+
+    fn discard(&mut self, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+        check_range_u64(self.size(), paddr, len)?;
+        // In range, so both fit in `usize` (the backing store is a single allocation).
+        let (start, end) = (paddr as usize, (paddr + len) as usize);
+        self.data[start..end].fill(0);
+        Ok(())
+    }
 }
 
 /// Sparse guest memory backed by lazily-allocated fixed-size chunks.
@@ -479,8 +526,13 @@ impl GuestMemory for SparseMemory {
             let bytes_in_chunk = self.chunk_size - chunk_off;
             let take = bytes_in_chunk.min(remaining.len());
 
-            let chunk = self.ensure_chunk(chunk_idx)?;
-            chunk[chunk_off..chunk_off + take].copy_from_slice(&remaining[..take]);
+            // Unallocated chunks already read as zero; don't commit storage just to store zeroes
+            // (e.g. when restoring a snapshot that contains discarded/ballooned pages).
+            let unallocated = matches!(self.chunks.get(chunk_idx), Some(None));
+            if !(unallocated && remaining[..take].iter().all(|&b| b == 0)) {
+                let chunk = self.ensure_chunk(chunk_idx)?;
+                chunk[chunk_off..chunk_off + take].copy_from_slice(&remaining[..take]);
+            }
 
             cur += take as u64;
             remaining = &remaining[take..];
@@ -508,6 +560,43 @@ impl GuestMemory for SparseMemory {
         let chunk = self.chunks.get_mut(chunk_idx)?.as_mut()?;
         Some(&mut chunk[chunk_off..chunk_off + len])
     }
+
+    fn discard(&mut self, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+        check_range_u64(self.size, paddr, len)?;
+        let end = paddr + len;
+        let mut cur = paddr;
+
+        while cur < end {
+            let (chunk_idx, chunk_off) = self.chunk_index(cur)?;
+            let bytes_in_chunk = (self.chunk_size - chunk_off) as u64;
+            let take = bytes_in_chunk.min(end - cur) as usize;
+
+            if let Some(slot) = self.chunks.get_mut(chunk_idx) {
+                // Release the chunk once it no longer holds any data; partially-discarded chunks
+                // are zeroed in place.
+                let release = match slot.as_mut() {
+                    None => false,
+                    Some(_) if take == self.chunk_size => true,
+                    Some(chunk) => {
+                        chunk[chunk_off..chunk_off + take].fill(0);
+                        chunk.iter().all(|&b| b == 0)
+                    }
+                };
+                if release {
+                    *slot = None;
+                }
+            }
+
+            cur += take as u64;
+        }
+
+        Ok(())
+    }
+
+    fn resident_bytes(&self) -> u64 {
+        let allocated = self.chunks.iter().filter(|c| c.is_some()).count() as u64;
+        allocated * self.chunk_size as u64
+    }
 }
 
 #[cfg(test)]
@@ -591,6 +680,51 @@ mod tests {
         assert_eq!(mem.read_u32_le(14).unwrap(), 0x1122_3344);
     }
 
+    #[test]
+    fn sparse_zero_writes_do_not_allocate() {
+        let mut mem = SparseMemory::with_chunk_size(64, 16).unwrap();
+        mem.write_from(0, &[0u8; 20]).unwrap();
+        assert_eq!(mem.resident_bytes(), 0);
+
+        mem.write_from(0, &[0, 1]).unwrap();
+        assert_eq!(mem.resident_bytes(), 16);
+    }
+
+    #[test]
+    fn sparse_discard_releases_chunks_without_data() {
+        let mut mem = SparseMemory::with_chunk_size(64, 16).unwrap();
+        mem.write_from(0, &[0xAA; 64]).unwrap();
+        assert_eq!(mem.resident_bytes(), 64);
+
+        // Whole chunk (16..32) plus part of the next one.
+        mem.discard(16, 20).unwrap();
+        assert_eq!(mem.resident_bytes(), 48);
+        assert_eq!(mem.read_u32_le(32).unwrap(), 0);
+        assert_eq!(mem.read_u8_le(36).unwrap(), 0xAA);
+
+        // Discarding the rest of chunk 2 leaves it empty, so it is released too.
+        mem.discard(36, 12).unwrap();
+        assert_eq!(mem.resident_bytes(), 32);
+        assert_eq!(mem.read_u8_le(15).unwrap(), 0xAA);
+        assert_eq!(mem.read_u8_le(48).unwrap(), 0xAA);
+
+        assert!(matches!(
+            mem.discard(60, 8),
+            Err(GuestMemoryError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn dense_discard_zeroes_range() {
+        let mut mem = DenseMemory::new(32).unwrap();
+        mem.write_from(0, &[0xAA; 32]).unwrap();
+        mem.discard(8, 16).unwrap();
+        assert_eq!(mem.read_u8_le(7).unwrap(), 0xAA);
+        assert_eq!(mem.read_u128_le(8).unwrap(), 0);
+        assert_eq!(mem.read_u8_le(24).unwrap(), 0xAA);
+        assert_eq!(mem.resident_bytes(), 32);
+    }
+
     #[test]
     fn out_of_range_returns_error_without_panicking() {
         let mut dense = DenseMemory::new(16).unwrap();
@@ -1,6 +1,13 @@
 use std::cell::Cell;
 use std::rc::Rc;
 
+/// Shared A20 gate latch.
+///
+/// A20 model: while the gate is disabled, address line 20 is forced low for every *CPU-originated*
+/// physical access (reads, writes and instruction fetches), regardless of whether it targets RAM,
+/// ROM or MMIO; this is applied centrally by [`crate::memory::MemoryBus`]. Bus-master DMA drives
+/// the address lines itself and is not gated, so device models access memory through
+/// [`crate::memory::MemoryBus::bus_master`], which ignores this latch.
 #[derive(Clone)]
 pub struct A20GateHandle(Rc<Cell<bool>>);
 
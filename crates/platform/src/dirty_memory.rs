@@ -193,6 +193,20 @@ impl GuestMemory for DirtyTrackingMemory {
         handle.mark_range(paddr, len);
         Some(slice)
     }
+
+    fn discard(&mut self, paddr: u64, len: u64) -> GuestMemoryResult<()> {
+        self.inner.discard(paddr, len)?;
+
+        // Discarded pages now read as zero, which is a guest RAM modification.
+        let handle = self.tracking_handle();
+        handle.mark_range(paddr, usize::try_from(len).unwrap_or(usize::MAX));
+
+        Ok(())
+    }
+
+    fn resident_bytes(&self) -> u64 {
+        self.inner.resident_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -124,7 +124,7 @@ fn ioapic_write_u32_bytes<C, R, W>(
 
 enum IoApicMmioBackend {
     Direct(Arc<Mutex<IoApic>>),
-    Platform(SharedPlatformInterrupts),
+    Platform(SharedPlatformInterrupts, usize),
 }
 
 /// MMIO adapter for [`IoApic`] compatible with `memory::PhysicalMemoryBus`.
@@ -148,8 +148,17 @@ impl IoApicMmio {
     ///
     /// See [`LapicMmio::from_platform_interrupts`] for motivation.
     pub fn from_platform_interrupts(interrupts: SharedPlatformInterrupts) -> Self {
+        Self::from_platform_interrupts_for_ioapic(interrupts, 0)
+    }
+
+    /// Like [`IoApicMmio::from_platform_interrupts`], but for IOAPIC `index` (1 is the second
+    /// IOAPIC of [`super::PlatformInterrupts::new_with_second_ioapic`]).
+    pub fn from_platform_interrupts_for_ioapic(
+        interrupts: SharedPlatformInterrupts,
+        index: usize,
+    ) -> Self {
         Self {
-            backend: IoApicMmioBackend::Platform(interrupts),
+            backend: IoApicMmioBackend::Platform(interrupts, index),
         }
     }
 }
@@ -166,10 +175,10 @@ impl MmioHandler for IoApicMmio {
                     ioapic.mmio_read(word_offset, 4) as u32
                 })
             }
-            IoApicMmioBackend::Platform(interrupts) => {
+            IoApicMmioBackend::Platform(interrupts, index) => {
                 let mut interrupts = interrupts.borrow_mut();
                 ioapic_read_u32_bytes(&mut *interrupts, offset, size, |ints, word_offset| {
-                    ints.ioapic_mmio_read(word_offset)
+                    ints.ioapic_mmio_read_for_ioapic(*index, word_offset)
                 })
             }
         }
@@ -193,15 +202,18 @@ impl MmioHandler for IoApicMmio {
                     },
                 );
             }
-            IoApicMmioBackend::Platform(interrupts) => {
+            IoApicMmioBackend::Platform(interrupts, index) => {
+                let index = *index;
                 let mut interrupts = interrupts.borrow_mut();
                 ioapic_write_u32_bytes(
                     &mut *interrupts,
                     offset,
                     size,
                     value,
-                    |ints, word_offset| ints.ioapic_mmio_read(word_offset),
-                    |ints, word_offset, word| ints.ioapic_mmio_write(word_offset, word),
+                    |ints, word_offset| ints.ioapic_mmio_read_for_ioapic(index, word_offset),
+                    |ints, word_offset, word| {
+                        ints.ioapic_mmio_write_for_ioapic(index, word_offset, word)
+                    },
                 );
             }
         }
@@ -2,7 +2,7 @@ use super::pic::Pic8259;
 use crate::io::{IoPortBus, PortIoDevice};
 use aero_interrupts::apic::{
     DeliveryMode, DestinationShorthand, Icr, IcrNotifier, IoApic, IoApicId, LapicInterruptSink,
-    Level, LocalApic,
+    Level, LocalApic, SECOND_IOAPIC_GSI_BASE,
 };
 use aero_interrupts::clock::Clock;
 use aero_interrupts::pic8259::{MASTER_DATA, SLAVE_DATA};
@@ -105,6 +105,9 @@ pub struct PlatformInterrupts {
 
     pic: Pic8259,
     ioapic: Arc<Mutex<IoApic>>,
+    /// Optional second IOAPIC serving GSIs `SECOND_IOAPIC_GSI_BASE..` (see
+    /// [`PlatformInterrupts::new_with_second_ioapic`]).
+    ioapic2: Option<Arc<Mutex<IoApic>>>,
     lapics: Vec<Arc<LocalApic>>,
     lapic_clock: Arc<AtomicClock>,
     apic_enabled: Arc<AtomicBool>,
@@ -127,6 +130,7 @@ impl std::fmt::Debug for PlatformInterrupts {
             .field("mode", &self.mode)
             .field("isa_irq_to_gsi", &self.isa_irq_to_gsi)
             .field("gsi_level", &self.gsi_level)
+            .field("ioapics", &self.ioapic_count())
             .field("lapics", &self.lapics.len())
             .field("pic", &self.pic)
             .field("imcr_select", &self.imcr_select)
@@ -179,6 +183,10 @@ impl PortIoDevice for ImcrPort {
             _ => interrupts.imcr_port_write(port, value as u8),
         }
     }
+
+    fn debug_name(&self) -> &'static str {
+        "imcr"
+    }
 }
 
 impl PlatformInterrupts {
@@ -192,6 +200,18 @@ impl PlatformInterrupts {
     /// `InterruptController` delivery default to LAPIC0), but we keep the full LAPIC set so SMP
     /// machine configurations can construct a stable topology.
     pub fn new_with_cpu_count(cpu_count: u8) -> Self {
+        Self::new_with_ioapics(cpu_count, false)
+    }
+
+    /// Like [`PlatformInterrupts::new_with_cpu_count`], but with a second IOAPIC (ID 1) serving
+    /// GSIs `SECOND_IOAPIC_GSI_BASE..SECOND_IOAPIC_GSI_BASE + 24`.
+    ///
+    /// All inputs of the second IOAPIC are wired active-low, like the PCI INTx lines routed to it.
+    pub fn new_with_second_ioapic(cpu_count: u8) -> Self {
+        Self::new_with_ioapics(cpu_count, true)
+    }
+
+    fn new_with_ioapics(cpu_count: u8, second_ioapic: bool) -> Self {
         let cpu_count = cpu_count.max(1);
         let mut isa_irq_to_gsi = [0u32; 16];
         for (idx, slot) in isa_irq_to_gsi.iter_mut().enumerate() {
@@ -225,13 +245,19 @@ impl PlatformInterrupts {
             lapics.push(lapic);
         }
         let pending_sipi = Arc::new(Mutex::new(vec![None; cpu_count as usize]));
+        let ioapic2 =
+            second_ioapic.then(|| Arc::new(Mutex::new(Self::build_second_ioapic(sinks.clone()))));
         let ioapic = Arc::new(Mutex::new(IoApic::with_lapics(IoApicId(0), sinks)));
 
         // Wire LAPIC EOI -> IOAPIC Remote-IRR handling.
         for lapic in &lapics {
             let ioapic_for_eoi = ioapic.clone();
+            let ioapic2_for_eoi = ioapic2.clone();
             lapic.register_eoi_notifier(Arc::new(move |vector| {
                 ioapic_for_eoi.lock().unwrap().notify_eoi(vector);
+                if let Some(ioapic2) = &ioapic2_for_eoi {
+                    ioapic2.lock().unwrap().notify_eoi(vector);
+                }
             }));
         }
 
@@ -258,7 +284,10 @@ impl PlatformInterrupts {
             }));
         }
 
-        let num_gsis = ioapic.lock().unwrap().num_redirection_entries();
+        let num_gsis = ioapic.lock().unwrap().num_redirection_entries()
+            + ioapic2.as_ref().map_or(0, |ioapic2| {
+                ioapic2.lock().unwrap().num_redirection_entries()
+            });
 
         // `Pic8259::new` programs vector offsets using the standard legacy init sequence.
         // The 8259A clears IMR during initialization (enabling all IRQ lines), which is not a
@@ -281,6 +310,7 @@ impl PlatformInterrupts {
 
             pic,
             ioapic,
+            ioapic2,
             lapics,
             lapic_clock,
             apic_enabled,
@@ -373,6 +403,14 @@ impl PlatformInterrupts {
         }
     }
 
+    fn build_second_ioapic(sinks: Vec<Arc<dyn LapicInterruptSink>>) -> IoApic {
+        let mut ioapic = IoApic::with_lapics(IoApicId(1), sinks);
+        for pin in 0..ioapic.num_redirection_entries() {
+            ioapic.set_pin_active_low(pin as u32, true);
+        }
+        ioapic
+    }
+
     fn enable_lapic_software(lapic: &LocalApic) {
         // `LocalApic::reset_state` models the power-on SVR value (0xFF with the software-enable
         // bit cleared). At the platform level we keep LAPICs enabled by default so external
@@ -480,9 +518,12 @@ impl PlatformInterrupts {
                 apic_enabled: self.apic_enabled.clone(),
             }));
         }
+        if let Some(ioapic2) = &self.ioapic2 {
+            *ioapic2.lock().unwrap() = Self::build_second_ioapic(sinks.clone());
+        }
         *self.ioapic.lock().unwrap() = IoApic::with_lapics(IoApicId(0), sinks);
 
-        let num_gsis = self.ioapic.lock().unwrap().num_redirection_entries();
+        let num_gsis = self.num_gsis();
         self.gsi_level = vec![false; num_gsis];
         self.gsi_restore_baseline = vec![false; num_gsis];
         self.gsi_assert_count = vec![0; num_gsis];
@@ -506,6 +547,33 @@ impl PlatformInterrupts {
         self.lapics.len()
     }
 
+    /// Number of IOAPICs (1, or 2 for [`PlatformInterrupts::new_with_second_ioapic`]).
+    pub fn ioapic_count(&self) -> usize {
+        1 + usize::from(self.ioapic2.is_some())
+    }
+
+    /// Number of GSIs served by the IOAPICs.
+    pub fn num_gsis(&self) -> usize {
+        self.ioapics()
+            .map(|ioapic| ioapic.lock().unwrap().num_redirection_entries())
+            .sum()
+    }
+
+    fn ioapics(&self) -> impl Iterator<Item = &Arc<Mutex<IoApic>>> + '_ {
+        std::iter::once(&self.ioapic).chain(self.ioapic2.as_ref())
+    }
+
+    /// Returns the IOAPIC serving `gsi` and the GSI's input pin on it.
+    fn ioapic_for_gsi(&self, gsi: u32) -> Option<(&Arc<Mutex<IoApic>>, u32)> {
+        match &self.ioapic2 {
+            Some(ioapic2) if gsi >= SECOND_IOAPIC_GSI_BASE => {
+                Some((ioapic2, gsi - SECOND_IOAPIC_GSI_BASE))
+            }
+            _ if gsi < SECOND_IOAPIC_GSI_BASE => Some((&self.ioapic, gsi)),
+            _ => None,
+        }
+    }
+
     /// Iterate over all local APICs (LAPICs) in APIC ID order.
     ///
     /// The platform stores LAPICs as `Arc<LocalApic>` to allow machine integrations to hold stable
@@ -553,10 +621,13 @@ impl PlatformInterrupts {
             // PIC (or has seen input levels change without delivery), Remote-IRR may not
             // represent a real in-service interrupt. Reset it before syncing asserted
             // level-triggered lines into the LAPIC.
-            let mut ioapic = self.ioapic.lock().unwrap();
-            ioapic.clear_remote_irr();
+            for ioapic in self.ioapics() {
+                ioapic.lock().unwrap().clear_remote_irr();
+            }
             for (gsi, level) in self.gsi_level.iter().enumerate() {
-                ioapic.set_irq_level(gsi as u32, *level);
+                if let Some((ioapic, pin)) = self.ioapic_for_gsi(gsi as u32) {
+                    ioapic.lock().unwrap().set_irq_level(pin, *level);
+                }
             }
         }
     }
@@ -652,11 +723,28 @@ impl PlatformInterrupts {
     }
 
     pub fn ioapic_mmio_read(&self, offset: u64) -> u32 {
-        self.ioapic.lock().unwrap().mmio_read(offset, 4) as u32
+        self.ioapic_mmio_read_for_ioapic(0, offset)
     }
 
     pub fn ioapic_mmio_write(&mut self, offset: u64, value: u32) {
-        let mut ioapic = self.ioapic.lock().unwrap();
+        self.ioapic_mmio_write_for_ioapic(0, offset, value);
+    }
+
+    /// Like [`PlatformInterrupts::ioapic_mmio_read`], but for IOAPIC `index` (0 or 1). Reads of
+    /// a missing IOAPIC return 0.
+    pub fn ioapic_mmio_read_for_ioapic(&self, index: usize, offset: u64) -> u32 {
+        let Some(ioapic) = self.ioapics().nth(index) else {
+            return 0;
+        };
+        ioapic.lock().unwrap().mmio_read(offset, 4) as u32
+    }
+
+    /// Like [`PlatformInterrupts::ioapic_mmio_write`], but for IOAPIC `index` (0 or 1).
+    pub fn ioapic_mmio_write_for_ioapic(&mut self, index: usize, offset: u64, value: u32) {
+        let Some(ioapic) = self.ioapics().nth(index) else {
+            return;
+        };
+        let mut ioapic = ioapic.lock().unwrap();
         ioapic.mmio_write(offset, 4, u64::from(value));
         if self.mode != PlatformInterruptMode::Apic {
             ioapic.clear_remote_irr();
@@ -920,7 +1008,9 @@ impl PlatformInterrupts {
                 }
             }
             PlatformInterruptMode::Apic => {
-                self.ioapic.lock().unwrap().set_irq_level(gsi, level);
+                if let Some((ioapic, pin)) = self.ioapic_for_gsi(gsi) {
+                    ioapic.lock().unwrap().set_irq_level(pin, level);
+                }
             }
         }
     }
@@ -982,6 +1072,7 @@ impl IoSnapshot for PlatformInterrupts {
         const TAG_GSI_LEVEL: u16 = 8;
         const TAG_LAPIC_CLOCK_NOW_NS: u16 = 9;
         const TAG_LAPICS: u16 = 10;
+        const TAG_IOAPIC2: u16 = 11;
 
         let mut w = SnapshotWriter::new(Self::DEVICE_ID, Self::DEVICE_VERSION);
 
@@ -1010,6 +1101,9 @@ impl IoSnapshot for PlatformInterrupts {
 
         w.field_bytes(TAG_PIC, self.pic.save_state());
         w.field_bytes(TAG_IOAPIC, self.ioapic.lock().unwrap().save_state());
+        if let Some(ioapic2) = &self.ioapic2 {
+            w.field_bytes(TAG_IOAPIC2, ioapic2.lock().unwrap().save_state());
+        }
         if self.lapics.len() == 1 {
             // Preserve the legacy single-LAPIC tag for smaller snapshots and backward
             // compatibility.
@@ -1037,6 +1131,7 @@ impl IoSnapshot for PlatformInterrupts {
         const TAG_GSI_LEVEL: u16 = 8;
         const TAG_LAPIC_CLOCK_NOW_NS: u16 = 9;
         const TAG_LAPICS: u16 = 10;
+        const TAG_IOAPIC2: u16 = 11;
 
         const MAX_SNAPSHOT_GSI_LEVELS: usize = 4096;
         const MAX_SNAPSHOT_LAPICS: usize = 256;
@@ -1124,6 +1219,9 @@ impl IoSnapshot for PlatformInterrupts {
         if let Some(buf) = r.bytes(TAG_IOAPIC) {
             self.ioapic.lock().unwrap().load_state(buf)?;
         }
+        if let (Some(buf), Some(ioapic2)) = (r.bytes(TAG_IOAPIC2), &self.ioapic2) {
+            ioapic2.lock().unwrap().load_state(buf)?;
+        }
         if let Some(buf) = r.bytes(TAG_LAPICS) {
             // SMP snapshots: restore LAPIC state by CPU index.
             //
@@ -1168,7 +1266,7 @@ impl IoSnapshot for PlatformInterrupts {
             self.lapics[0].restore_state(buf)?;
         }
 
-        let num_gsis = self.ioapic.lock().unwrap().num_redirection_entries();
+        let num_gsis = self.num_gsis();
         self.gsi_level.resize(num_gsis, false);
         self.gsi_restore_baseline.resize(num_gsis, false);
         self.gsi_assert_count.resize(num_gsis, 0);
@@ -1178,7 +1276,9 @@ impl IoSnapshot for PlatformInterrupts {
             //
             // This avoids losing interrupts on restore without clearing Remote-IRR; the IOAPIC
             // implementation gates level-triggered delivery on Remote-IRR.
-            self.ioapic.lock().unwrap().sync_level_triggered();
+            for ioapic in self.ioapics() {
+                ioapic.lock().unwrap().sync_level_triggered();
+            }
         }
 
         // Invalidate cached `PlatformIrqLine` state. Restored devices will re-drive their own line
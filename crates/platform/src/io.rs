@@ -1,17 +1,51 @@
+pub use memory::{AccessPolicy, AccessSizes};
+
 pub trait PortIoDevice {
     fn read(&mut self, port: u16, size: u8) -> u32;
     fn write(&mut self, port: u16, size: u8, value: u32);
 
     /// Reset the device back to its power-on state.
     fn reset(&mut self) {}
+
+    /// Short, stable name of the device model decoding this port, used by debug views such as
+    /// [`IoPortBus::claims`].
+    fn debug_name(&self) -> &'static str {
+        "unknown"
+    }
 }
 
+/// A contiguous run of I/O ports decoded by one device model (see [`IoPortBus::claims`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPortClaim {
+    pub start: u16,
+    /// Last decoded port (inclusive).
+    pub end: u16,
+    pub owner: &'static str,
+    /// Whether the claim comes from a range device ([`IoPortBus::register_range`]) rather than
+    /// exact-port handlers.
+    pub range: bool,
+}
+
+/// Observer for port accesses no device decodes (see [`IoPortBus::set_unclaimed_hook`]).
+///
+/// Called with `(port, size, is_write)`.
+pub type UnclaimedPortHook = Box<dyn FnMut(u16, u8, bool)>;
+
 const IO_PORT_COUNT: usize = 0x1_0000;
-type IoPortTable = [Option<Box<dyn PortIoDevice>>; IO_PORT_COUNT];
+type IoPortTable = [Option<PortSlot>; IO_PORT_COUNT];
+
+/// Exact-port handler and the access policy of the registration it belongs to.
+struct PortSlot {
+    dev: Box<dyn PortIoDevice>,
+    policy: AccessPolicy,
+    /// Port policy alignment is relative to (the port itself for exact-port registrations).
+    region_start: u16,
+}
 
 struct RangeDevice {
     start: u16,
     len: u16,
+    policy: AccessPolicy,
     dev: Box<dyn PortIoDevice>,
 }
 
@@ -33,22 +67,34 @@ impl RangeDevice {
 ///
 /// For a Criterion microbench harness, see `crates/platform/benches/io_port_bus.rs` (run with
 /// `--features bench`).
+///
+/// # Access policies
+///
+/// Every registration carries an [`AccessPolicy`] (the `*_with_policy` variants; the plain ones
+/// use [`AccessPolicy::Passthrough`], which forwards every access as issued). An access is
+/// legalized by the policy of the registration decoding its first port. Alignment is relative to
+/// the first port of a range device, while exact ports (including shared ranges) are each their
+/// own region, so an access is aligned whenever it starts at the port. The first legal piece is
+/// delivered to that device; later pieces address `port + pos` and are dispatched through the bus
+/// again, so an access straddling the end of a registration reaches whatever decodes the
+/// following ports. Rejected pieces float high on reads and are dropped on writes.
 pub struct IoPortBus {
     /// Exact port dispatch table.
     ///
     /// This is an intentionally fixed-size table (one entry per 16-bit I/O port) to keep port
     /// dispatch O(1) and avoid hashing overhead on the extremely hot port I/O path.
     ///
-    /// (Memory note: `Option<Box<dyn PortIoDevice>>` is a fat pointer, so this is ~1MiB on
-    /// 64-bit targets; allocated once per bus.)
+    /// (Memory note: each slot holds a fat `Box<dyn PortIoDevice>` plus its access policy, so this
+    /// is ~1.5MiB on 64-bit targets; allocated once per bus.)
     devices: Box<IoPortTable>,
     ranges: Vec<RangeDevice>,
+    unclaimed_hook: Option<UnclaimedPortHook>,
 }
 
 impl IoPortBus {
     pub fn new() -> Self {
-        // `vec![None; IO_PORT_COUNT]` doesn't work here because `Option<Box<dyn PortIoDevice>>`
-        // isn't `Clone`. Build the fixed table with `resize_with` instead.
+        // `vec![None; IO_PORT_COUNT]` doesn't work here because `Option<PortSlot>` isn't `Clone`.
+        // Build the fixed table with `resize_with` instead.
         let mut devices = Vec::with_capacity(IO_PORT_COUNT);
         devices.resize_with(IO_PORT_COUNT, || None);
 
@@ -58,11 +104,42 @@ impl IoPortBus {
                 Err(_) => panic!("I/O port dispatch table size mismatch"),
             },
             ranges: Vec::new(),
+            unclaimed_hook: None,
+        }
+    }
+
+    /// Install (`Some`) or remove (`None`) an observer for accesses to ports with no registered
+    /// device.
+    ///
+    /// The hook only observes: unclaimed reads still float high (all ones) and unclaimed writes
+    /// are still dropped. Accesses with an invalid size never reach it.
+    pub fn set_unclaimed_hook(&mut self, hook: Option<UnclaimedPortHook>) {
+        self.unclaimed_hook = hook;
+    }
+
+    fn notify_unclaimed(&mut self, port: u16, size: u8, is_write: bool) {
+        if let Some(hook) = self.unclaimed_hook.as_mut() {
+            hook(port, size, is_write);
         }
     }
 
     pub fn register(&mut self, port: u16, device: Box<dyn PortIoDevice>) {
-        self.devices[usize::from(port)] = Some(device);
+        self.register_with_policy(port, AccessPolicy::Passthrough, device);
+    }
+
+    /// Like [`Self::register`], but accesses starting at `port` are legalized through `policy`
+    /// before they reach `device` (see [Access policies](Self#access-policies)).
+    pub fn register_with_policy(
+        &mut self,
+        port: u16,
+        policy: AccessPolicy,
+        device: Box<dyn PortIoDevice>,
+    ) {
+        self.devices[usize::from(port)] = Some(PortSlot {
+            dev: device,
+            policy,
+            region_start: port,
+        });
     }
 
     /// Unregister an I/O port handler, returning the removed device (if any).
@@ -72,7 +149,7 @@ impl IoPortBus {
     /// mapped BAR range and re-register it at the new base without rebuilding the
     /// entire bus.
     pub fn unregister(&mut self, port: u16) -> Option<Box<dyn PortIoDevice>> {
-        self.devices[usize::from(port)].take()
+        self.devices[usize::from(port)].take().map(|slot| slot.dev)
     }
 
     /// Unregister a contiguous range of I/O ports.
@@ -115,13 +192,32 @@ impl IoPortBus {
     /// The provided factory is invoked once per port. It can be used to build
     /// per-port wrapper devices that share a single underlying implementation
     /// (e.g. via `Rc<RefCell<...>>`).
-    pub fn register_shared_range<F>(&mut self, start: u16, len: u16, mut make: F)
+    pub fn register_shared_range<F>(&mut self, start: u16, len: u16, make: F)
     where
         F: FnMut(u16) -> Box<dyn PortIoDevice>,
+    {
+        self.register_shared_range_with_policy(start, len, AccessPolicy::Passthrough, make);
+    }
+
+    /// Like [`Self::register_shared_range`], but accesses are legalized through `policy`. Every
+    /// port is its own region, as if registered via [`Self::register_with_policy`] (see
+    /// [Access policies](Self#access-policies)).
+    pub fn register_shared_range_with_policy<F>(
+        &mut self,
+        start: u16,
+        len: u16,
+        policy: AccessPolicy,
+        mut make: F,
+    ) where
+        F: FnMut(u16) -> Box<dyn PortIoDevice>,
     {
         for offset in 0..len {
             let port = start.wrapping_add(offset);
-            self.register(port, make(port));
+            self.devices[usize::from(port)] = Some(PortSlot {
+                dev: make(port),
+                policy,
+                region_start: port,
+            });
         }
     }
 
@@ -131,6 +227,19 @@ impl IoPortBus {
     /// historical behavior for fixed legacy devices registered via [`Self::register`].
     #[track_caller]
     pub fn register_range(&mut self, start: u16, len: u16, dev: Box<dyn PortIoDevice>) {
+        self.register_range_with_policy(start, len, AccessPolicy::Passthrough, dev);
+    }
+
+    /// Like [`Self::register_range`], but accesses are legalized through `policy`, with alignment
+    /// relative to `start` (see [Access policies](Self#access-policies)).
+    #[track_caller]
+    pub fn register_range_with_policy(
+        &mut self,
+        start: u16,
+        len: u16,
+        policy: AccessPolicy,
+        dev: Box<dyn PortIoDevice>,
+    ) {
         assert!(len != 0, "I/O port range length must be non-zero");
 
         let end_exclusive = u32::from(start) + u32::from(len);
@@ -161,7 +270,15 @@ impl IoPortBus {
             );
         }
 
-        self.ranges.insert(idx, RangeDevice { start, len, dev });
+        self.ranges.insert(
+            idx,
+            RangeDevice {
+                start,
+                len,
+                policy,
+                dev,
+            },
+        );
     }
 
     fn find_range_index(&self, port: u16) -> Option<usize> {
@@ -189,25 +306,31 @@ impl IoPortBus {
         if !matches!(size, 1 | 2 | 4) {
             return 0xFFFF_FFFF;
         }
-        if let Some(dev) = self.devices[usize::from(port)].as_mut() {
-            return dev.read(port, size);
-        }
 
-        if let Some(idx) = self.find_range_index(port) {
-            return self
-                .ranges
-                .get_mut(idx)
-                .expect("range index disappeared")
-                .dev
-                .read(port, size);
+        let Some(decoder) = self.decoder(port) else {
+            self.notify_unclaimed(port, size, false);
+            return all_ones(size);
+        };
+        let (policy, region_start) = self.decoded_policy(port, decoder);
+        if policy == AccessPolicy::Passthrough {
+            return self.decoded_device(port, decoder).read(port, size);
         }
 
-        match size {
-            1 => 0xFF,
-            2 => 0xFFFF,
-            4 => 0xFFFF_FFFF,
-            _ => 0xFFFF_FFFF,
+        let mut value = 0u32;
+        for piece in policy.split(u64::from(port - region_start), usize::from(size)) {
+            let piece_size = piece.size as u8;
+            let piece_value = match (piece.accepted, port.checked_add(piece.pos as u16)) {
+                (true, Some(_)) if piece.pos == 0 => {
+                    self.decoded_device(port, decoder).read(port, piece_size)
+                }
+                // Later pieces may belong to a different decoder (or none); route them through
+                // the bus again. They are strictly smaller than the original access.
+                (true, Some(piece_port)) => self.read(piece_port, piece_size),
+                _ => all_ones(piece_size),
+            };
+            value |= (piece_value & all_ones(piece_size)) << (8 * piece.pos);
         }
+        value
     }
 
     pub fn write(&mut self, port: u16, size: u8, value: u32) {
@@ -217,17 +340,58 @@ impl IoPortBus {
         if !matches!(size, 1 | 2 | 4) {
             return;
         }
-        if let Some(device) = self.devices[usize::from(port)].as_mut() {
-            device.write(port, size, value);
+
+        let Some(decoder) = self.decoder(port) else {
+            self.notify_unclaimed(port, size, true);
             return;
+        };
+        let (policy, region_start) = self.decoded_policy(port, decoder);
+        if policy == AccessPolicy::Passthrough {
+            self.decoded_device(port, decoder).write(port, size, value);
+            return;
+        }
+
+        for piece in policy.split(u64::from(port - region_start), usize::from(size)) {
+            let piece_size = piece.size as u8;
+            let piece_value = (value >> (8 * piece.pos)) & all_ones(piece_size);
+            match (piece.accepted, port.checked_add(piece.pos as u16)) {
+                (true, Some(_)) if piece.pos == 0 => {
+                    self.decoded_device(port, decoder)
+                        .write(port, piece_size, piece_value)
+                }
+                (true, Some(piece_port)) => self.write(piece_port, piece_size, piece_value),
+                _ => {}
+            }
+        }
+    }
+
+    fn decoder(&self, port: u16) -> Option<Decoder> {
+        if self.devices[usize::from(port)].is_some() {
+            return Some(Decoder::Port);
         }
+        self.find_range_index(port).map(Decoder::Range)
+    }
 
-        if let Some(idx) = self.find_range_index(port) {
-            self.ranges
-                .get_mut(idx)
-                .expect("range index disappeared")
+    /// Access policy and region start of the registration `decoder` found for `port`.
+    fn decoded_policy(&self, port: u16, decoder: Decoder) -> (AccessPolicy, u16) {
+        match decoder {
+            Decoder::Port => {
+                let slot = self.devices[usize::from(port)].as_ref().expect("slot");
+                (slot.policy, slot.region_start)
+            }
+            Decoder::Range(idx) => (self.ranges[idx].policy, self.ranges[idx].start),
+        }
+    }
+
+    /// Device behind the registration `decoder` found for `port`.
+    fn decoded_device(&mut self, port: u16, decoder: Decoder) -> &mut dyn PortIoDevice {
+        match decoder {
+            Decoder::Port => self.devices[usize::from(port)]
+                .as_mut()
+                .expect("slot disappeared")
                 .dev
-                .write(port, size, value);
+                .as_mut(),
+            Decoder::Range(idx) => self.ranges[idx].dev.as_mut(),
         }
     }
 
@@ -239,9 +403,45 @@ impl IoPortBus {
         self.write(port, 1, value as u32);
     }
 
+    /// Lists the ports currently decoded, sorted by start port.
+    ///
+    /// Adjacent exact-port handlers reporting the same [`PortIoDevice::debug_name`] are merged
+    /// into one claim. Range devices are reported as-is; exact ports shadow them during dispatch
+    /// but the claims may overlap here.
+    pub fn claims(&self) -> Vec<IoPortClaim> {
+        let mut out: Vec<IoPortClaim> = Vec::new();
+        for (port, slot) in self.devices.iter().enumerate() {
+            let Some(slot) = slot else {
+                continue;
+            };
+            let port = port as u16;
+            let owner = slot.dev.debug_name();
+            if let Some(last) = out.last_mut() {
+                if last.owner == owner && u32::from(last.end) + 1 == u32::from(port) {
+                    last.end = port;
+                    continue;
+                }
+            }
+            out.push(IoPortClaim {
+                start: port,
+                end: port,
+                owner,
+                range: false,
+            });
+        }
+        out.extend(self.ranges.iter().map(|r| IoPortClaim {
+            start: r.start,
+            end: (r.end_exclusive() - 1) as u16,
+            owner: r.dev.debug_name(),
+            range: true,
+        }));
+        out.sort_by_key(|c| (c.start, c.end));
+        out
+    }
+
     pub fn reset(&mut self) {
-        for dev in self.devices.iter_mut().filter_map(|d| d.as_mut()) {
-            dev.reset();
+        for slot in self.devices.iter_mut().filter_map(|d| d.as_mut()) {
+            slot.dev.reset();
         }
 
         for dev in self.ranges.iter_mut() {
@@ -250,6 +450,20 @@ impl IoPortBus {
     }
 }
 
+#[derive(Clone, Copy)]
+enum Decoder {
+    Port,
+    Range(usize),
+}
+
+fn all_ones(size: u8) -> u32 {
+    match size {
+        1 => 0xFF,
+        2 => 0xFFFF,
+        _ => 0xFFFF_FFFF,
+    }
+}
+
 impl Default for IoPortBus {
     fn default() -> Self {
         Self::new()
@@ -687,4 +901,214 @@ mod tests {
         assert_eq!(bus.read(0x1234, 4), 0x1234_5678);
         assert_eq!(reads.get(), 1);
     }
+
+    #[test]
+    fn claims_merge_adjacent_exact_ports_and_report_range_devices() {
+        struct Named(&'static str);
+
+        impl PortIoDevice for Named {
+            fn read(&mut self, _port: u16, _size: u8) -> u32 {
+                0
+            }
+
+            fn write(&mut self, _port: u16, _size: u8, _value: u32) {}
+
+            fn debug_name(&self) -> &'static str {
+                self.0
+            }
+        }
+
+        let mut bus = IoPortBus::new();
+        bus.register_shared_range(0x40, 4, |_| Box::new(Named("pit")));
+        bus.register(0x44, Box::new(Named("other")));
+        bus.register(0x60, Box::new(Named("pit")));
+        bus.register_range(0x3000, 0x100, Box::new(Named("window")));
+        bus.register(0x3010, Box::new(ExactValue));
+
+        assert_eq!(
+            bus.claims(),
+            vec![
+                IoPortClaim {
+                    start: 0x40,
+                    end: 0x43,
+                    owner: "pit",
+                    range: false,
+                },
+                IoPortClaim {
+                    start: 0x44,
+                    end: 0x44,
+                    owner: "other",
+                    range: false,
+                },
+                IoPortClaim {
+                    start: 0x60,
+                    end: 0x60,
+                    owner: "pit",
+                    range: false,
+                },
+                IoPortClaim {
+                    start: 0x3000,
+                    end: 0x30FF,
+                    owner: "window",
+                    range: true,
+                },
+                IoPortClaim {
+                    start: 0x3010,
+                    end: 0x3010,
+                    owner: "unknown",
+                    range: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unclaimed_hook_sees_only_undecoded_ports() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        let mut bus = IoPortBus::new();
+        bus.register(0x10, Box::new(ExactValue));
+        bus.register_range(0x3000, 0x10, Box::new(ExactValue));
+        bus.set_unclaimed_hook(Some(Box::new(move |port: u16, size: u8, write: bool| {
+            sink.borrow_mut().push((port, size, write));
+        })));
+
+        bus.read(0x10, 1);
+        bus.write(0x3004, 2, 0);
+        assert_eq!(bus.read(0x20, 2), 0xFFFF);
+        bus.write(0x21, 4, 0);
+        // Invalid sizes are rejected before dispatch.
+        bus.read(0x22, 3);
+        assert_eq!(*seen.borrow(), [(0x20, 2, false), (0x21, 4, true)]);
+    }
+
+    /// `(port, size, Some(value) for writes)` of every access a [`LogPort`] received.
+    type AccessLog = Rc<RefCell<Vec<(u16, u8, Option<u32>)>>>;
+
+    /// Logs every access it receives and reads back `0xA0 + port offset` per byte.
+    #[derive(Clone)]
+    struct LogPort {
+        log: AccessLog,
+        base: u16,
+    }
+
+    impl PortIoDevice for LogPort {
+        fn read(&mut self, port: u16, size: u8) -> u32 {
+            self.log.borrow_mut().push((port, size, None));
+            (0..u32::from(size)).fold(0, |acc, i| {
+                let byte = 0xA0 + u32::from(port - self.base) + i;
+                acc | (byte << (8 * i))
+            })
+        }
+
+        fn write(&mut self, port: u16, size: u8, value: u32) {
+            self.log.borrow_mut().push((port, size, Some(value)));
+        }
+    }
+
+    #[test]
+    fn split_policy_breaks_accesses_into_bytes_and_straddles_into_the_next_decoder() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = IoPortBus::new();
+        let dev = LogPort {
+            log: log.clone(),
+            base: 0x3C0,
+        };
+        bus.register_shared_range_with_policy(
+            0x3C0,
+            4,
+            AccessPolicy::Split(AccessSizes::BYTE),
+            |_| Box::new(dev.clone()),
+        );
+        let next = LogPort {
+            log: log.clone(),
+            base: 0x3C4,
+        };
+        bus.register(0x3C4, Box::new(next));
+
+        bus.write(0x3C0, 2, 0x1122);
+        assert_eq!(bus.read(0x3C1, 2), 0xA2A1);
+        // Straddles into a passthrough port and then unclaimed space, still one byte at a time.
+        assert_eq!(bus.read(0x3C3, 4), 0xFFFF_A0A3);
+        assert_eq!(
+            *log.borrow(),
+            [
+                (0x3C0, 1, Some(0x22)),
+                (0x3C1, 1, Some(0x11)),
+                (0x3C1, 1, None),
+                (0x3C2, 1, None),
+                (0x3C3, 1, None),
+                (0x3C4, 1, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_policy_rejects_odd_sizes_and_misaligned_range_accesses() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = IoPortBus::new();
+        let word_port = LogPort {
+            log: log.clone(),
+            base: 0x1CF,
+        };
+        // Exact ports are their own region, so odd port numbers are still aligned.
+        bus.register_with_policy(
+            0x1CF,
+            AccessPolicy::Natural(AccessSizes::WORD),
+            Box::new(word_port),
+        );
+        let range = LogPort {
+            log: log.clone(),
+            base: 0x4000,
+        };
+        bus.register_range_with_policy(
+            0x4000,
+            8,
+            AccessPolicy::Natural(AccessSizes::DWORD),
+            Box::new(range),
+        );
+
+        assert_eq!(bus.read(0x1CF, 2), 0xA1A0);
+        assert_eq!(bus.read(0x1CF, 1), 0xFF);
+        assert_eq!(bus.read(0x1CF, 4), 0xFFFF_FFFF);
+        bus.write(0x1CF, 1, 0);
+        assert_eq!(bus.read(0x4004, 4), 0xA7A6_A5A4);
+        assert_eq!(bus.read(0x4002, 4), 0xFFFF_FFFF);
+        bus.write(0x4000, 2, 0);
+        assert_eq!(*log.borrow(), [(0x1CF, 2, None), (0x4004, 4, None)]);
+    }
+
+    #[test]
+    fn split_policy_straddles_end_of_range_and_io_space() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = IoPortBus::new();
+        let sizes = AccessSizes::BYTE | AccessSizes::WORD | AccessSizes::DWORD;
+        let cfg = LogPort {
+            log: log.clone(),
+            base: 0xCFC,
+        };
+        bus.register_range_with_policy(0xCFC, 4, AccessPolicy::Split(sizes), Box::new(cfg));
+        let top = LogPort {
+            log: log.clone(),
+            base: 0xFFFE,
+        };
+        bus.register_range_with_policy(0xFFFE, 2, AccessPolicy::Split(sizes), Box::new(top));
+
+        assert_eq!(bus.read(0xCFD, 2), 0xA2A1);
+        assert_eq!(bus.read(0xCFE, 4), 0xFFFF_A3A2);
+        bus.write(0xCFD, 4, 0x4433_2211);
+        // Bytes past port 0xFFFF float high / are dropped.
+        assert_eq!(bus.read(0xFFFF, 4), 0xFFFF_FFA1);
+        assert_eq!(
+            *log.borrow(),
+            [
+                (0xCFD, 1, None),
+                (0xCFE, 1, None),
+                (0xCFE, 2, None),
+                (0xCFD, 1, Some(0x11)),
+                (0xCFE, 2, Some(0x3322)),
+                (0xFFFF, 1, None),
+            ]
+        );
+    }
 }
@@ -3,8 +3,8 @@ use crate::chipset::A20GateHandle;
 use crate::dirty_memory::{DirtyTrackingHandle, DirtyTrackingMemory, DEFAULT_DIRTY_PAGE_SIZE};
 use aero_pc_constants::PCIE_ECAM_BASE;
 use memory::{
-    DenseMemory, GuestMemory, GuestMemoryMapping, MapError, MappedGuestMemory, MmioHandler,
-    PhysicalMemoryBus,
+    AccessPolicy, DenseMemory, GuestMemory, GuestMemoryMapping, MapError, MappedGuestMemory,
+    MmioHandler, PhysicalMemoryBus, RomWriteHook, UnmappedAccessHook,
 };
 use std::sync::Arc;
 
@@ -26,7 +26,8 @@ const A20_BOUNDARY_MASK: u64 = A20_BIT - 1;
 /// - RAM is backed by a [`memory::GuestMemory`] implementation.
 /// - ROM is read-only and may be mapped at multiple aliases (e.g. BIOS).
 /// - MMIO takes precedence over ROM and RAM.
-/// - A20 gating is applied to *all* physical accesses when disabled.
+/// - A20 gating is applied to all CPU-side physical accesses when disabled; device DMA uses the
+///   unmasked [`MemoryBus::bus_master`] view (see [`A20GateHandle`]).
 pub struct MemoryBus {
     filter: AddressFilter,
     bus: PhysicalMemoryBus,
@@ -196,6 +197,18 @@ impl MemoryBus {
         self.bus.map_mmio(start, len, handler)
     }
 
+    /// Like [`Self::map_mmio`], but accesses are legalized through `policy` before they reach
+    /// `handler` (see [`memory::AccessPolicy`]).
+    pub fn map_mmio_with_policy(
+        &mut self,
+        start: u64,
+        len: u64,
+        policy: AccessPolicy,
+        handler: Box<dyn MmioHandler>,
+    ) -> Result<(), MapError> {
+        self.bus.map_mmio_with_policy(start, len, policy, handler)
+    }
+
     /// Map the system BIOS ROM into the conventional `F0000..=FFFFF` legacy window and the
     /// top-of-4GiB reset-vector alias `FFFF_0000..=FFFF_FFFF`.
     ///
@@ -213,6 +226,30 @@ impl MemoryBus {
         Ok(())
     }
 
+    /// Bus-master (device DMA) view of the physical address space.
+    ///
+    /// Accesses are routed to RAM, ROM and MMIO exactly like CPU accesses, except that A20
+    /// masking is not applied.
+    pub fn bus_master(&mut self) -> BusMasterMemory<'_> {
+        BusMasterMemory { bus: &mut self.bus }
+    }
+
+    /// Install (`Some`) or remove (`None`) an observer for accesses that hit no RAM, ROM or MMIO
+    /// mapping (see [`PhysicalMemoryBus::set_unmapped_access_hook`]).
+    ///
+    /// The hook sees post-A20 addresses and fires for [`MemoryBus::bus_master`] accesses too.
+    pub fn set_unmapped_access_hook(&mut self, hook: Option<UnmappedAccessHook>) {
+        self.bus.set_unmapped_access_hook(hook);
+    }
+
+    /// Install (`Some`) or remove (`None`) an observer for writes that hit a ROM mapping (see
+    /// [`PhysicalMemoryBus::set_rom_write_hook`]).
+    ///
+    /// Like the unmapped-access hook, it sees post-A20 addresses and bus-master writes.
+    pub fn set_rom_write_hook(&mut self, hook: Option<RomWriteHook>) {
+        self.bus.set_rom_write_hook(hook);
+    }
+
     pub fn read_u8(&mut self, paddr: u64) -> u8 {
         let mut buf = [0u8; 1];
         self.read_physical(paddr, &mut buf);
@@ -302,6 +339,21 @@ impl memory::MemoryBus for MemoryBus {
     }
 }
 
+/// Unmasked physical memory view for bus masters; see [`MemoryBus::bus_master`].
+pub struct BusMasterMemory<'a> {
+    bus: &'a mut PhysicalMemoryBus,
+}
+
+impl memory::MemoryBus for BusMasterMemory<'_> {
+    fn read_physical(&mut self, paddr: u64, buf: &mut [u8]) {
+        self.bus.read_physical(paddr, buf);
+    }
+
+    fn write_physical(&mut self, paddr: u64, buf: &[u8]) {
+        self.bus.write_physical(paddr, buf);
+    }
+}
+
 impl aero_mmu::MemoryBus for MemoryBus {
     #[inline]
     fn read_u8(&mut self, paddr: u64) -> u8 {
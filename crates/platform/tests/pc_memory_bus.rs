@@ -4,6 +4,7 @@ use aero_platform::dirty_memory::DEFAULT_DIRTY_PAGE_SIZE;
 use aero_platform::memory::{MemoryBus, BIOS_RESET_VECTOR_PHYS, BIOS_ROM_BASE, BIOS_ROM_SIZE};
 use aero_platform::ChipsetState;
 use memory::MapError;
+use memory::MemoryBus as _;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
@@ -449,6 +450,59 @@ fn a20_masking_does_not_apply_to_direct_ram_backend_access() {
     assert_eq!(bus.ram().read_u8_le(0x1_00000).unwrap(), 0x44);
 }
 
+#[test]
+fn a20_disabled_wrap_at_1mib_reads_ivt() {
+    let mut bus = new_bus(false, 2 * 1024 * 1024);
+
+    // Real-mode code addressing FFFF:0010 (physical 0x10_0000) must see the interrupt vector
+    // table, not the first byte of extended memory.
+    bus.ram_mut()
+        .write_from(0x0, &0x1234_5678u32.to_le_bytes())
+        .unwrap();
+    bus.ram_mut()
+        .write_from(0x10_0000, &0xDEAD_BEEFu32.to_le_bytes())
+        .unwrap();
+
+    assert_eq!(bus.read_u32(0x10_0000), 0x1234_5678);
+    let mut fetch = [0u8; 4];
+    bus.read_physical(0x10_0000, &mut fetch);
+    assert_eq!(u32::from_le_bytes(fetch), 0x1234_5678);
+}
+
+#[test]
+fn a20_disabled_aliases_system_bios_rom_above_1mib() {
+    let chipset = ChipsetState::new(true);
+    let mut bus = MemoryBus::new(AddressFilter::new(chipset.a20()), 2 * 1024 * 1024);
+
+    let mut rom = vec![0u8; BIOS_ROM_SIZE];
+    rom[BIOS_ROM_SIZE - 16..BIOS_ROM_SIZE - 11].copy_from_slice(&[0xEA, 0x5B, 0xE0, 0x00, 0xF0]);
+    bus.map_system_bios_rom(Arc::<[u8]>::from(rom)).unwrap();
+    bus.ram_mut().write_u8_le(0x1F_FFF0, 0x90).unwrap();
+
+    // With A20 enabled, 0x1F_FFF0 is ordinary RAM.
+    assert_eq!(bus.read_u8(0x1F_FFF0), 0x90);
+
+    // With A20 disabled it aliases the legacy reset vector at 0xF_FFF0.
+    chipset.a20().set_enabled(false);
+    let mut buf = [0u8; 5];
+    bus.read_physical(0x1F_FFF0, &mut buf);
+    assert_eq!(buf, [0xEA, 0x5B, 0xE0, 0x00, 0xF0]);
+}
+
+#[test]
+fn bus_master_dma_is_not_a20_masked() {
+    let mut bus = new_bus(false, 2 * 1024 * 1024);
+    bus.ram_mut().write_u8_le(0x0, 0x11).unwrap();
+
+    // A bus-master write above 1MiB lands at its true physical address even with A20 disabled.
+    bus.bus_master().write_u32(0x10_0000, 0xCAFE_F00D);
+    assert_eq!(bus.ram().read_u8_le(0x0).unwrap(), 0x11);
+    assert_eq!(bus.bus_master().read_u32(0x10_0000), 0xCAFE_F00D);
+
+    // The CPU still observes the wrapped alias.
+    assert_eq!(bus.read_u8(0x10_0000), 0x11);
+}
+
 #[test]
 fn a20_disabled_bulk_reads_match_byte_wise_across_1mib_boundary() {
     let mut bus = new_bus(false, 2 * 1024 * 1024);
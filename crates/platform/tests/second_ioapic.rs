@@ -0,0 +1,102 @@
+use aero_interrupts::apic::SECOND_IOAPIC_GSI_BASE;
+use aero_io_snapshot::io::state::IoSnapshot;
+use aero_platform::interrupts::{
+    InterruptController, InterruptInput, PlatformInterruptMode, PlatformInterrupts,
+};
+
+const ACTIVE_LOW: u32 = 1 << 13;
+const REMOTE_IRR: u32 = 1 << 14;
+const LEVEL: u32 = 1 << 15;
+
+/// Programs an unmasked entry for `pin` of IOAPIC `index`, targeting APIC ID 0.
+fn program_entry(ints: &mut PlatformInterrupts, index: usize, pin: u32, low: u32) {
+    let redtbl_low = 0x10 + pin * 2;
+    ints.ioapic_mmio_write_for_ioapic(index, 0x00, redtbl_low);
+    ints.ioapic_mmio_write_for_ioapic(index, 0x10, low);
+    ints.ioapic_mmio_write_for_ioapic(index, 0x00, redtbl_low + 1);
+    ints.ioapic_mmio_write_for_ioapic(index, 0x10, 0);
+}
+
+fn read_entry_low(ints: &mut PlatformInterrupts, index: usize, pin: u32) -> u32 {
+    ints.ioapic_mmio_write_for_ioapic(index, 0x00, 0x10 + pin * 2);
+    ints.ioapic_mmio_read_for_ioapic(index, 0x10)
+}
+
+#[test]
+fn second_ioapic_reports_its_id_and_size() {
+    let mut ints = PlatformInterrupts::new_with_second_ioapic(1);
+    assert_eq!(ints.ioapic_count(), 2);
+    assert_eq!(ints.num_gsis(), 48);
+
+    ints.ioapic_mmio_write_for_ioapic(1, 0x00, 0x00);
+    assert_eq!(ints.ioapic_mmio_read_for_ioapic(1, 0x10), 1 << 24);
+    ints.ioapic_mmio_write_for_ioapic(1, 0x00, 0x01);
+    assert_eq!(ints.ioapic_mmio_read_for_ioapic(1, 0x10), 0x0017_0011);
+
+    let single = PlatformInterrupts::new();
+    assert_eq!(single.ioapic_count(), 1);
+    assert_eq!(single.num_gsis(), 24);
+    assert_eq!(single.ioapic_mmio_read_for_ioapic(1, 0x10), 0);
+}
+
+#[test]
+fn gsi_26_delivers_through_second_ioapic_redirection_table() {
+    let gsi = 26;
+    let pin = gsi - SECOND_IOAPIC_GSI_BASE;
+    let mut ints = PlatformInterrupts::new_with_second_ioapic(1);
+    ints.set_mode(PlatformInterruptMode::Apic);
+    program_entry(&mut ints, 1, pin, 0x61 | ACTIVE_LOW | LEVEL);
+    // The first IOAPIC's entry with the same pin number must not be involved.
+    program_entry(&mut ints, 0, pin, 0x51 | LEVEL);
+
+    ints.raise_irq(InterruptInput::Gsi(gsi));
+    assert!(ints.gsi_level(gsi));
+    assert_eq!(ints.get_pending(), Some(0x61));
+    ints.acknowledge(0x61);
+    assert_ne!(read_entry_low(&mut ints, 1, pin) & REMOTE_IRR, 0);
+    assert_eq!(read_entry_low(&mut ints, 0, pin) & REMOTE_IRR, 0);
+
+    // EOI with the line still asserted redelivers; after deasserting it stays quiet.
+    ints.eoi(0x61);
+    assert_eq!(ints.get_pending(), Some(0x61));
+    ints.acknowledge(0x61);
+    ints.lower_irq(InterruptInput::Gsi(gsi));
+    ints.eoi(0x61);
+    assert_eq!(read_entry_low(&mut ints, 1, pin) & REMOTE_IRR, 0);
+    assert_eq!(ints.get_pending(), None);
+}
+
+#[test]
+fn gsis_beyond_a_single_ioapic_are_ignored() {
+    let mut ints = PlatformInterrupts::new();
+    ints.set_mode(PlatformInterruptMode::Apic);
+    program_entry(&mut ints, 0, 2, 0x51 | LEVEL);
+
+    ints.raise_irq(InterruptInput::Gsi(26));
+    assert!(!ints.gsi_level(26));
+    assert_eq!(ints.get_pending(), None);
+}
+
+#[test]
+fn second_ioapic_state_survives_snapshot_and_reset() {
+    let gsi = 30;
+    let pin = gsi - SECOND_IOAPIC_GSI_BASE;
+    let mut ints = PlatformInterrupts::new_with_second_ioapic(1);
+    ints.set_mode(PlatformInterruptMode::Apic);
+    program_entry(&mut ints, 1, pin, 0x70 | ACTIVE_LOW | LEVEL);
+    let programmed = read_entry_low(&mut ints, 1, pin);
+
+    let bytes = ints.save_state();
+    let mut restored = PlatformInterrupts::new_with_second_ioapic(1);
+    restored.load_state(&bytes).unwrap();
+    restored.finalize_restore();
+    assert_eq!(read_entry_low(&mut restored, 1, pin), programmed);
+
+    restored.raise_irq(InterruptInput::Gsi(gsi));
+    assert_eq!(restored.get_pending(), Some(0x70));
+
+    restored.reset();
+    assert_eq!(restored.ioapic_count(), 2);
+    // Redirection entries power on masked.
+    assert_eq!(read_entry_low(&mut restored, 1, pin), 1 << 16);
+}
@@ -1436,6 +1436,16 @@ static const char* AeroGpuErrorCodeName(_In_ ULONG Code)
             return "OOB";
         case AEROGPU_ERROR_BACKEND:
             return "BACKEND";
+        case AEROGPU_ERROR_CMD_STREAM_HEADER:
+            return "CMD_STREAM_HEADER";
+        case AEROGPU_ERROR_PACKET_ALIGNMENT:
+            return "PACKET_ALIGNMENT";
+        case AEROGPU_ERROR_PACKET_SIZE:
+            return "PACKET_SIZE";
+        case AEROGPU_ERROR_ALLOC_TABLE:
+            return "ALLOC_TABLE";
+        case AEROGPU_ERROR_FENCE_ORDER:
+            return "FENCE_ORDER";
         case AEROGPU_ERROR_INTERNAL:
             return "INTERNAL";
         default:
@@ -117,6 +117,8 @@ AEROGPU_STATIC_ASSERT(AEROGPU_PCI_BAR1_VBE_LFB_OFFSET_BYTES < AEROGPU_PCI_BAR1_S
 #define AEROGPU_FEATURE_VBLANK (1ull << 3) /* Implements vblank IRQ + vblank timing regs */
 #define AEROGPU_FEATURE_TRANSFER (1ull << 4) /* Supports transfer/copy commands + optional guest writeback (ABI 1.1+) */
 #define AEROGPU_FEATURE_ERROR_INFO (1ull << 5) /* Implements error-info MMIO registers (ABI 1.3+) */
+#define AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE (1ull << 6) /* VBLANK_PERIOD_NS may change at runtime; raises AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED */
+#define AEROGPU_FEATURE_MSIX (1ull << 7) /* Exposes an MSI-X capability with per-cause vectors (see below) */
 
 /* Ring setup */
 #define AEROGPU_MMIO_REG_RING_GPA_LO 0x0100u /* RW: GPA of aerogpu_ring_header */
@@ -147,6 +149,7 @@ AEROGPU_STATIC_ASSERT(AEROGPU_PCI_BAR1_VBE_LFB_OFFSET_BYTES < AEROGPU_PCI_BAR1_S
 /* IRQ_STATUS / IRQ_ENABLE bits */
 #define AEROGPU_IRQ_FENCE (1u << 0) /* Completed fence advanced */
 #define AEROGPU_IRQ_SCANOUT_VBLANK (1u << 1) /* Scanout vblank tick (if AEROGPU_FEATURE_VBLANK) */
+#define AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED (1u << 2) /* VBLANK_PERIOD_NS changed; re-read it (if AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE) */
 #define AEROGPU_IRQ_ERROR (1u << 31) /* Fatal device error */
 
 /*
@@ -187,6 +190,35 @@ AEROGPU_STATIC_ASSERT(AEROGPU_PCI_BAR1_VBE_LFB_OFFSET_BYTES < AEROGPU_PCI_BAR1_S
 #define AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_HI 0x042Cu /* RO */
 #define AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS 0x0430u /* RO: nominal period in ns */
 
+/*
+ * When AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE is set, the host may change the
+ * vblank rate while scanout is running. The new period takes effect at the
+ * next vblank edge (VBLANK_SEQ stays monotonic) and the device latches
+ * AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED in IRQ_STATUS if it is enabled.
+ */
+
+/*
+ * MSI-X (if AEROGPU_FEATURE_MSIX is set).
+ *
+ * The PCI function exposes an MSI-X capability whose table and PBA live in
+ * BAR0. Each interrupt cause has its own vector so the driver can register
+ * per-vector ISRs. IRQ_STATUS / IRQ_ENABLE / IRQ_ACK keep their meaning: a
+ * cause is only signalled if enabled in IRQ_ENABLE, and IRQ_STATUS still
+ * latches it. While MSI-X is enabled the legacy INTx line is never asserted;
+ * with MSI-X disabled the device falls back to INTx.
+ */
+#define AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES 0x8000u
+#define AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES 0x9000u
+
+#define AEROGPU_MSIX_VECTOR_FENCE 0u /* AEROGPU_IRQ_FENCE */
+#define AEROGPU_MSIX_VECTOR_VBLANK 1u /* AEROGPU_IRQ_SCANOUT_VBLANK, AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED */
+#define AEROGPU_MSIX_VECTOR_ERROR 2u /* AEROGPU_IRQ_ERROR */
+#define AEROGPU_MSIX_VECTOR_COUNT 3u
+
+AEROGPU_STATIC_ASSERT(AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES >=
+                      AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES + AEROGPU_MSIX_VECTOR_COUNT * 16u);
+AEROGPU_STATIC_ASSERT(AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES < AEROGPU_PCI_BAR0_SIZE_BYTES);
+
 /* Cursor configuration (reserved if AEROGPU_FEATURE_CURSOR == 0) */
 #define AEROGPU_MMIO_REG_CURSOR_ENABLE 0x0500u /* RW */
 #define AEROGPU_MMIO_REG_CURSOR_X 0x0504u /* RW: signed 32-bit */
@@ -213,6 +245,16 @@ enum aerogpu_error_code {
   AEROGPU_ERROR_CMD_DECODE = 1,
   AEROGPU_ERROR_OOB = 2,
   AEROGPU_ERROR_BACKEND = 3,
+  /*
+   * Strict submission validation (host debug option, see
+   * docs/16-gpu-command-abi.md). Only reported when the host enables it; the
+   * offending submission's fence is not completed.
+   */
+  AEROGPU_ERROR_CMD_STREAM_HEADER = 4, /* bad cmd stream magic/ABI/size */
+  AEROGPU_ERROR_PACKET_ALIGNMENT = 5, /* packet size_bytes not 4-byte aligned */
+  AEROGPU_ERROR_PACKET_SIZE = 6, /* packet too small or overruns the stream */
+  AEROGPU_ERROR_ALLOC_TABLE = 7, /* bad alloc table or unknown alloc_id reference */
+  AEROGPU_ERROR_FENCE_ORDER = 8, /* signal_fence lower than a previous submission's */
   AEROGPU_ERROR_INTERNAL = 0xFFFFu,
 };
 
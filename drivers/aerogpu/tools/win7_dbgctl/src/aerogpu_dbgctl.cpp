@@ -488,6 +488,16 @@ static const wchar_t *AerogpuErrorCodeName(uint32_t code) {
     return L"OOB";
   case AEROGPU_ERROR_BACKEND:
     return L"BACKEND";
+  case AEROGPU_ERROR_CMD_STREAM_HEADER:
+    return L"CMD_STREAM_HEADER";
+  case AEROGPU_ERROR_PACKET_ALIGNMENT:
+    return L"PACKET_ALIGNMENT";
+  case AEROGPU_ERROR_PACKET_SIZE:
+    return L"PACKET_SIZE";
+  case AEROGPU_ERROR_ALLOC_TABLE:
+    return L"ALLOC_TABLE";
+  case AEROGPU_ERROR_FENCE_ORDER:
+    return L"FENCE_ORDER";
   case AEROGPU_ERROR_INTERNAL:
     return L"INTERNAL";
   default:
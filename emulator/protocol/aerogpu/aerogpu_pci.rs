@@ -96,6 +96,8 @@ pub const AEROGPU_FEATURE_SCANOUT: u64 = 1u64 << 2;
 pub const AEROGPU_FEATURE_VBLANK: u64 = 1u64 << 3;
 pub const AEROGPU_FEATURE_TRANSFER: u64 = 1u64 << 4;
 pub const AEROGPU_FEATURE_ERROR_INFO: u64 = 1u64 << 5;
+pub const AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE: u64 = 1u64 << 6;
+pub const AEROGPU_FEATURE_MSIX: u64 = 1u64 << 7;
 
 pub const AEROGPU_MMIO_REG_RING_GPA_LO: u32 = 0x0100;
 pub const AEROGPU_MMIO_REG_RING_GPA_HI: u32 = 0x0104;
@@ -119,6 +121,7 @@ pub const AEROGPU_MMIO_REG_IRQ_ACK: u32 = 0x0308;
 
 pub const AEROGPU_IRQ_FENCE: u32 = 1u32 << 0;
 pub const AEROGPU_IRQ_SCANOUT_VBLANK: u32 = 1u32 << 1;
+pub const AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED: u32 = 1u32 << 2;
 pub const AEROGPU_IRQ_ERROR: u32 = 1u32 << 31;
 
 // Error reporting (ABI 1.3+).
@@ -141,6 +144,15 @@ pub const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_LO: u32 = 0x0428;
 pub const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_HI: u32 = 0x042C;
 pub const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS: u32 = 0x0430;
 
+// MSI-X (if `AEROGPU_FEATURE_MSIX`): table + PBA in BAR0, one vector per interrupt cause.
+pub const AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES: u32 = 0x8000;
+pub const AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES: u32 = 0x9000;
+
+pub const AEROGPU_MSIX_VECTOR_FENCE: u32 = 0;
+pub const AEROGPU_MSIX_VECTOR_VBLANK: u32 = 1;
+pub const AEROGPU_MSIX_VECTOR_ERROR: u32 = 2;
+pub const AEROGPU_MSIX_VECTOR_COUNT: u32 = 3;
+
 pub const AEROGPU_MMIO_REG_CURSOR_ENABLE: u32 = 0x0500;
 pub const AEROGPU_MMIO_REG_CURSOR_X: u32 = 0x0504;
 pub const AEROGPU_MMIO_REG_CURSOR_Y: u32 = 0x0508;
@@ -162,6 +174,11 @@ pub enum AerogpuErrorCode {
     CmdDecode = 1,
     Oob = 2,
     Backend = 3,
+    CmdStreamHeader = 4,
+    PacketAlignment = 5,
+    PacketSize = 6,
+    AllocTable = 7,
+    FenceOrder = 8,
     Internal = 0xFFFF,
 }
 
@@ -79,6 +79,8 @@ export const AEROGPU_FEATURE_SCANOUT = 1n << 2n;
 export const AEROGPU_FEATURE_VBLANK = 1n << 3n;
 export const AEROGPU_FEATURE_TRANSFER = 1n << 4n;
 export const AEROGPU_FEATURE_ERROR_INFO = 1n << 5n;
+export const AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE = 1n << 6n;
+export const AEROGPU_FEATURE_MSIX = 1n << 7n;
 
 export const AEROGPU_MMIO_REG_RING_GPA_LO = 0x0100;
 export const AEROGPU_MMIO_REG_RING_GPA_HI = 0x0104;
@@ -102,6 +104,7 @@ export const AEROGPU_MMIO_REG_IRQ_ACK = 0x0308;
 
 export const AEROGPU_IRQ_FENCE = 1 << 0;
 export const AEROGPU_IRQ_SCANOUT_VBLANK = 1 << 1;
+export const AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED = 1 << 2;
 // NOTE: avoid `1 << 31` (signed 32-bit) which yields a negative number in JS.
 export const AEROGPU_IRQ_ERROR = 0x8000_0000;
 
@@ -125,6 +128,15 @@ export const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_LO = 0x0428;
 export const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_HI = 0x042c;
 export const AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS = 0x0430;
 
+// MSI-X (if `AEROGPU_FEATURE_MSIX`): table + PBA in BAR0, one vector per interrupt cause.
+export const AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES = 0x8000;
+export const AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES = 0x9000;
+
+export const AEROGPU_MSIX_VECTOR_FENCE = 0;
+export const AEROGPU_MSIX_VECTOR_VBLANK = 1;
+export const AEROGPU_MSIX_VECTOR_ERROR = 2;
+export const AEROGPU_MSIX_VECTOR_COUNT = 3;
+
 export const AEROGPU_MMIO_REG_CURSOR_ENABLE = 0x0500;
 export const AEROGPU_MMIO_REG_CURSOR_X = 0x0504;
 export const AEROGPU_MMIO_REG_CURSOR_Y = 0x0508;
@@ -144,6 +156,11 @@ export const AerogpuErrorCode = {
   CmdDecode: 1,
   Oob: 2,
   Backend: 3,
+  CmdStreamHeader: 4,
+  PacketAlignment: 5,
+  PacketSize: 6,
+  AllocTable: 7,
+  FenceOrder: 8,
   Internal: 0xffff,
 } as const;
 
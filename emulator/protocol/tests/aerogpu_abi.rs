@@ -3888,6 +3888,46 @@ fn rust_layout_matches_c_headers() {
         "AEROGPU_FEATURE_ERROR_INFO",
         AEROGPU_FEATURE_ERROR_INFO,
     );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE",
+        pci::AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_FEATURE_MSIX",
+        pci::AEROGPU_FEATURE_MSIX,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES",
+        pci::AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES",
+        pci::AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_MSIX_VECTOR_FENCE",
+        pci::AEROGPU_MSIX_VECTOR_FENCE as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_MSIX_VECTOR_VBLANK",
+        pci::AEROGPU_MSIX_VECTOR_VBLANK as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_MSIX_VECTOR_ERROR",
+        pci::AEROGPU_MSIX_VECTOR_ERROR as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_MSIX_VECTOR_COUNT",
+        pci::AEROGPU_MSIX_VECTOR_COUNT as u64,
+    );
 
     check_const(
         &mut pci_consts_seen,
@@ -3995,6 +4035,11 @@ fn rust_layout_matches_c_headers() {
         "AEROGPU_IRQ_SCANOUT_VBLANK",
         pci::AEROGPU_IRQ_SCANOUT_VBLANK as u64,
     );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED",
+        pci::AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED as u64,
+    );
     check_const(
         &mut pci_consts_seen,
         "AEROGPU_IRQ_ERROR",
@@ -4182,6 +4227,31 @@ fn rust_layout_matches_c_headers() {
         "AEROGPU_ERROR_BACKEND",
         AerogpuErrorCode::Backend as u64,
     );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_ERROR_CMD_STREAM_HEADER",
+        AerogpuErrorCode::CmdStreamHeader as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_ERROR_PACKET_ALIGNMENT",
+        AerogpuErrorCode::PacketAlignment as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_ERROR_PACKET_SIZE",
+        AerogpuErrorCode::PacketSize as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_ERROR_ALLOC_TABLE",
+        AerogpuErrorCode::AllocTable as u64,
+    );
+    check_const(
+        &mut pci_consts_seen,
+        "AEROGPU_ERROR_FENCE_ORDER",
+        AerogpuErrorCode::FenceOrder as u64,
+    );
     check_const(
         &mut pci_consts_seen,
         "AEROGPU_ERROR_INTERNAL",
@@ -1253,6 +1253,27 @@ test("TypeScript layout matches C headers", () => {
   assert.equal(konst("AEROGPU_FEATURE_VBLANK"), AEROGPU_FEATURE_VBLANK);
   assert.equal(konst("AEROGPU_FEATURE_TRANSFER"), AEROGPU_FEATURE_TRANSFER);
   assert.equal(konst("AEROGPU_FEATURE_ERROR_INFO"), AEROGPU_FEATURE_ERROR_INFO);
+  assert.equal(
+    konst("AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE"),
+    aerogpuPci.AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE,
+  );
+  assert.equal(
+    konst("AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED"),
+    BigInt(aerogpuPci.AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED),
+  );
+  assert.equal(konst("AEROGPU_FEATURE_MSIX"), aerogpuPci.AEROGPU_FEATURE_MSIX);
+  assert.equal(
+    konst("AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES"),
+    BigInt(aerogpuPci.AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES),
+  );
+  assert.equal(
+    konst("AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES"),
+    BigInt(aerogpuPci.AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES),
+  );
+  assert.equal(konst("AEROGPU_MSIX_VECTOR_FENCE"), BigInt(aerogpuPci.AEROGPU_MSIX_VECTOR_FENCE));
+  assert.equal(konst("AEROGPU_MSIX_VECTOR_VBLANK"), BigInt(aerogpuPci.AEROGPU_MSIX_VECTOR_VBLANK));
+  assert.equal(konst("AEROGPU_MSIX_VECTOR_ERROR"), BigInt(aerogpuPci.AEROGPU_MSIX_VECTOR_ERROR));
+  assert.equal(konst("AEROGPU_MSIX_VECTOR_COUNT"), BigInt(aerogpuPci.AEROGPU_MSIX_VECTOR_COUNT));
   assert.equal(konst("AEROGPU_RING_CONTROL_ENABLE"), BigInt(AEROGPU_RING_CONTROL_ENABLE));
   assert.equal(konst("AEROGPU_IRQ_FENCE"), BigInt(AEROGPU_IRQ_FENCE));
   assert.equal(
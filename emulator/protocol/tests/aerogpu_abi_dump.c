@@ -879,16 +879,25 @@ int main(void) {
   PRINT_CONST(AEROGPU_FEATURE_VBLANK);
   PRINT_CONST(AEROGPU_FEATURE_TRANSFER);
   PRINT_CONST(AEROGPU_FEATURE_ERROR_INFO);
+  PRINT_CONST(AEROGPU_FEATURE_VBLANK_PERIOD_CHANGE);
+  PRINT_CONST(AEROGPU_FEATURE_MSIX);
   PRINT_CONST(AEROGPU_RING_CONTROL_ENABLE);
   PRINT_CONST(AEROGPU_RING_CONTROL_RESET);
   PRINT_CONST(AEROGPU_IRQ_FENCE);
   PRINT_CONST(AEROGPU_IRQ_SCANOUT_VBLANK);
+  PRINT_CONST(AEROGPU_IRQ_SCANOUT_VBLANK_PERIOD_CHANGED);
   PRINT_CONST(AEROGPU_IRQ_ERROR);
   PRINT_CONST(AEROGPU_MMIO_REG_SCANOUT0_VBLANK_SEQ_LO);
   PRINT_CONST(AEROGPU_MMIO_REG_SCANOUT0_VBLANK_SEQ_HI);
   PRINT_CONST(AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_LO);
   PRINT_CONST(AEROGPU_MMIO_REG_SCANOUT0_VBLANK_TIME_NS_HI);
   PRINT_CONST(AEROGPU_MMIO_REG_SCANOUT0_VBLANK_PERIOD_NS);
+  PRINT_CONST(AEROGPU_PCI_BAR0_MSIX_TABLE_OFFSET_BYTES);
+  PRINT_CONST(AEROGPU_PCI_BAR0_MSIX_PBA_OFFSET_BYTES);
+  PRINT_CONST(AEROGPU_MSIX_VECTOR_FENCE);
+  PRINT_CONST(AEROGPU_MSIX_VECTOR_VBLANK);
+  PRINT_CONST(AEROGPU_MSIX_VECTOR_ERROR);
+  PRINT_CONST(AEROGPU_MSIX_VECTOR_COUNT);
 
   PRINT_CONST(AEROGPU_MMIO_REG_CURSOR_ENABLE);
   PRINT_CONST(AEROGPU_MMIO_REG_CURSOR_X);
@@ -1081,6 +1090,11 @@ int main(void) {
   PRINT_CONST(AEROGPU_ERROR_CMD_DECODE);
   PRINT_CONST(AEROGPU_ERROR_OOB);
   PRINT_CONST(AEROGPU_ERROR_BACKEND);
+  PRINT_CONST(AEROGPU_ERROR_CMD_STREAM_HEADER);
+  PRINT_CONST(AEROGPU_ERROR_PACKET_ALIGNMENT);
+  PRINT_CONST(AEROGPU_ERROR_PACKET_SIZE);
+  PRINT_CONST(AEROGPU_ERROR_ALLOC_TABLE);
+  PRINT_CONST(AEROGPU_ERROR_FENCE_ORDER);
   PRINT_CONST(AEROGPU_ERROR_INTERNAL);
 
   PRINT_CONST(AEROGPU_FORMAT_INVALID);
@@ -80,6 +80,7 @@ pub fn convert(opts: ConvertOptions) -> anyhow::Result<()> {
                 AeroSparseConfig {
                     disk_size_bytes: capacity,
                     block_size_bytes: opts.block_size_bytes,
+                    ..Default::default()
                 },
             )?;
             let pb = maybe_progress_bar(opts.progress, capacity, "aerosparse")?;
@@ -4718,6 +4718,7 @@ mod tests {
             AeroSparseConfig {
                 disk_size_bytes,
                 block_size_bytes: 4096,
+                ..Default::default()
             },
         )?;
         disk.write_at(0, b"hello")?;
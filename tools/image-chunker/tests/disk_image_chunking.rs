@@ -32,6 +32,7 @@ fn manifest_sha256_matches_chunk_bytes_for_aerosparse() {
         AeroSparseConfig {
             disk_size_bytes: disk_size,
             block_size_bytes: 4 * 1024,
+            ..Default::default()
         },
     )
     .expect("create aerosparse");
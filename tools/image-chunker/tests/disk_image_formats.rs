@@ -322,6 +322,7 @@ fn chunking_aerosparse_uses_virtual_disk_bytes() {
         AeroSparseConfig {
             disk_size_bytes,
             block_size_bytes: 4096,
+            ..Default::default()
         },
     )
     .unwrap();
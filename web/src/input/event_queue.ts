@@ -66,6 +66,18 @@ export const InputEventType = {
     * (and mirrored by the native emulator stack under `crates/emulator`).
     */
   GamepadReport: 5,
+  /**
+   * Absolute pointer position on a scanout head (delivered via the virtio-input tablet).
+   *
+   * Coordinates are pixels of the head; the machine scales them into the tablet range using the
+   * head's current scanout resolution.
+   *
+   * Payload:
+   *   a = (x & 0xFFFF) | ((y & 0xFFFF) << 16)
+   *   b = (head & 0xFF) | (1 << 8) to target a specific head, or 0 for the focused head
+   *       (see `Machine.set_input_focus`)
+   */
+  MouseAbsolute: 8,
 } as const;
 
 export type InputEventType = (typeof InputEventType)[keyof typeof InputEventType];
@@ -201,6 +213,12 @@ export class InputEventQueue {
     this.push(InputEventType.MouseWheel, timestampUs, dz | 0, dx | 0);
   }
 
+  pushMouseAbsolute(timestampUs: number, x: number, y: number, head?: number): void {
+    const a = ((x & 0xffff) | ((y & 0xffff) << 16)) | 0;
+    const b = head === undefined ? 0 : ((head & 0xff) | (1 << 8));
+    this.push(InputEventType.MouseAbsolute, timestampUs, a, b);
+  }
+
   pushGamepadReport(timestampUs: number, packedLo: number, packedHi: number): void {
     this.push(InputEventType.GamepadReport, timestampUs, packedLo | 0, packedHi | 0);
   }
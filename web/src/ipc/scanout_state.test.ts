@@ -45,7 +45,15 @@ describe("ipc/scanout_state", () => {
 
     expect((snap.generation & SCANOUT_STATE_GENERATION_BUSY_BIT) >>> 0).toBe(0);
     expect(snap.generation >>> 0).toBe(generation >>> 0);
-    expect(snap).toEqual({ generation: generation >>> 0, ...update });
+    expect(snap).toEqual({
+      generation: generation >>> 0,
+      ...update,
+      flags: 0,
+      frontIndex: 0,
+      frameSeq: 0,
+      slot0Offset: 0,
+      slot1Offset: 0,
+    });
   });
 
   it("scanout format constants match AerogpuFormat discriminants", () => {
@@ -13,6 +13,9 @@ import { AerogpuFormat } from "../../../emulator/protocol/aerogpu/aerogpu_pci.ts
 export const SCANOUT_SOURCE_LEGACY_TEXT = 0 as const;
 export const SCANOUT_SOURCE_LEGACY_VBE_LFB = 1 as const;
 export const SCANOUT_SOURCE_WDDM = 2 as const;
+// Host-composed RGBA8 frames stored in double-buffered frame slots (see `ScanoutFrameSlots` in Rust);
+// `basePaddr*` are unused.
+export const SCANOUT_SOURCE_FRAME_SLOTS = 3 as const;
 
 // Scanout format values use the AeroGPU `AerogpuFormat` numeric (`u32`) discriminants.
 //
@@ -37,11 +40,15 @@ export const SCANOUT_FORMAT_B8G8R8A8_SRGB: AerogpuFormat = AerogpuFormat.B8G8R8A
 export const SCANOUT_FORMAT_R8G8B8A8_SRGB: AerogpuFormat = AerogpuFormat.R8G8B8A8UnormSrgb;
 export const SCANOUT_FORMAT_R8G8B8X8_SRGB: AerogpuFormat = AerogpuFormat.R8G8B8X8UnormSrgb;
 
-export const SCANOUT_STATE_U32_LEN = 8 as const;
+export const SCANOUT_STATE_U32_LEN = 12 as const;
 export const SCANOUT_STATE_BYTE_LEN = SCANOUT_STATE_U32_LEN * 4;
 
 export const SCANOUT_STATE_GENERATION_BUSY_BIT = 0x8000_0000 as const;
 
+// Set in `FLAGS` when the published frame slots are distinct (the producer never writes the slot a
+// consumer may be reading). Cleared for single-buffered fallback and guest-memory scanouts.
+export const SCANOUT_STATE_FLAG_DOUBLE_BUFFERED = 1 << 0;
+
 export const ScanoutStateIndex = {
   GENERATION: 0,
   SOURCE: 1,
@@ -51,6 +58,11 @@ export const ScanoutStateIndex = {
   HEIGHT: 5,
   PITCH_BYTES: 6,
   FORMAT: 7,
+  FLAGS: 8,
+  // Packed `frameSeq << 1 | frontIndex`.
+  FRONT: 9,
+  SLOT0_OFFSET: 10,
+  SLOT1_OFFSET: 11,
 } as const;
 
 export type ScanoutStateIndex = (typeof ScanoutStateIndex)[keyof typeof ScanoutStateIndex];
@@ -63,10 +75,18 @@ export interface ScanoutStateUpdate {
   height: number;
   pitchBytes: number;
   format: AerogpuFormat;
+  // Frame slot byte offsets (`SCANOUT_SOURCE_FRAME_SLOTS` only); default 0.
+  slot0Offset?: number;
+  slot1Offset?: number;
 }
 
 export interface ScanoutStateSnapshot extends ScanoutStateUpdate {
   generation: number;
+  flags: number;
+  frontIndex: number;
+  frameSeq: number;
+  slot0Offset: number;
+  slot1Offset: number;
 }
 
 export interface TrySnapshotScanoutStateOptions {
@@ -144,6 +164,10 @@ export function snapshotScanoutState(words: Int32Array): ScanoutStateSnapshot {
     const height = Atomics.load(words, ScanoutStateIndex.HEIGHT) >>> 0;
     const pitchBytes = Atomics.load(words, ScanoutStateIndex.PITCH_BYTES) >>> 0;
     const format = (Atomics.load(words, ScanoutStateIndex.FORMAT) >>> 0) as AerogpuFormat;
+    const flags = Atomics.load(words, ScanoutStateIndex.FLAGS) >>> 0;
+    const front = Atomics.load(words, ScanoutStateIndex.FRONT) >>> 0;
+    const slot0Offset = Atomics.load(words, ScanoutStateIndex.SLOT0_OFFSET) >>> 0;
+    const slot1Offset = Atomics.load(words, ScanoutStateIndex.SLOT1_OFFSET) >>> 0;
 
     const gen1 = Atomics.load(words, ScanoutStateIndex.GENERATION) >>> 0;
     if (gen0 !== gen1) {
@@ -162,6 +186,11 @@ export function snapshotScanoutState(words: Int32Array): ScanoutStateSnapshot {
       height,
       pitchBytes,
       format,
+      flags,
+      frontIndex: front & 1,
+      frameSeq: front >>> 1,
+      slot0Offset,
+      slot1Offset,
     };
   }
 
@@ -229,6 +258,10 @@ export function trySnapshotScanoutState(words: Int32Array, options?: TrySnapshot
     const height = Atomics.load(words, ScanoutStateIndex.HEIGHT) >>> 0;
     const pitchBytes = Atomics.load(words, ScanoutStateIndex.PITCH_BYTES) >>> 0;
     const format = (Atomics.load(words, ScanoutStateIndex.FORMAT) >>> 0) as AerogpuFormat;
+    const flags = Atomics.load(words, ScanoutStateIndex.FLAGS) >>> 0;
+    const front = Atomics.load(words, ScanoutStateIndex.FRONT) >>> 0;
+    const slot0Offset = Atomics.load(words, ScanoutStateIndex.SLOT0_OFFSET) >>> 0;
+    const slot1Offset = Atomics.load(words, ScanoutStateIndex.SLOT1_OFFSET) >>> 0;
 
     const gen1 = Atomics.load(words, ScanoutStateIndex.GENERATION) >>> 0;
     if (gen0 !== gen1) {
@@ -244,6 +277,11 @@ export function trySnapshotScanoutState(words: Int32Array, options?: TrySnapshot
       height,
       pitchBytes,
       format,
+      flags,
+      frontIndex: front & 1,
+      frameSeq: front >>> 1,
+      slot0Offset,
+      slot1Offset,
     };
   }
 
@@ -281,6 +319,13 @@ export function tryPublishScanoutState(words: Int32Array, update: ScanoutStateUp
       Atomics.store(words, ScanoutStateIndex.HEIGHT, update.height | 0);
       Atomics.store(words, ScanoutStateIndex.PITCH_BYTES, update.pitchBytes | 0);
       Atomics.store(words, ScanoutStateIndex.FORMAT, update.format | 0);
+      const slot0Offset = (update.slot0Offset ?? 0) >>> 0;
+      const slot1Offset = (update.slot1Offset ?? 0) >>> 0;
+      const flags =
+        update.source === SCANOUT_SOURCE_FRAME_SLOTS && slot0Offset !== slot1Offset ? SCANOUT_STATE_FLAG_DOUBLE_BUFFERED : 0;
+      Atomics.store(words, ScanoutStateIndex.FLAGS, flags);
+      Atomics.store(words, ScanoutStateIndex.SLOT0_OFFSET, slot0Offset | 0);
+      Atomics.store(words, ScanoutStateIndex.SLOT1_OFFSET, slot1Offset | 0);
 
       // Final publish step: increment generation and clear the busy bit.
       const newGeneration = (((start + 1) >>> 0) & (~SCANOUT_STATE_GENERATION_BUSY_BIT >>> 0)) >>> 0;
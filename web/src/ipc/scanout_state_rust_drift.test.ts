@@ -10,10 +10,12 @@ import {
   SCANOUT_FORMAT_R8G8B8A8_SRGB,
   SCANOUT_FORMAT_R8G8B8X8,
   SCANOUT_FORMAT_R8G8B8X8_SRGB,
+  SCANOUT_SOURCE_FRAME_SLOTS,
   SCANOUT_SOURCE_LEGACY_TEXT,
   SCANOUT_SOURCE_LEGACY_VBE_LFB,
   SCANOUT_SOURCE_WDDM,
   SCANOUT_STATE_BYTE_LEN,
+  SCANOUT_STATE_FLAG_DOUBLE_BUFFERED,
   SCANOUT_STATE_GENERATION_BUSY_BIT,
   SCANOUT_STATE_U32_LEN,
   ScanoutStateIndex,
@@ -142,6 +144,12 @@ describe("ScanoutState layout matches Rust source of truth", () => {
       parseRustConstNumber(rust, "SCANOUT_SOURCE_LEGACY_VBE_LFB"),
     );
     expect(SCANOUT_SOURCE_WDDM, "SCANOUT_SOURCE_WDDM mismatch (Rust <-> TS)").toBe(parseRustConstNumber(rust, "SCANOUT_SOURCE_WDDM"));
+    expect(SCANOUT_SOURCE_FRAME_SLOTS, "SCANOUT_SOURCE_FRAME_SLOTS mismatch (Rust <-> TS)").toBe(
+      parseRustConstNumber(rust, "SCANOUT_SOURCE_FRAME_SLOTS"),
+    );
+    expect(SCANOUT_STATE_FLAG_DOUBLE_BUFFERED, "SCANOUT_STATE_FLAG_DOUBLE_BUFFERED mismatch (Rust <-> TS)").toBe(
+      parseRustConstNumber(rust, "SCANOUT_STATE_FLAG_DOUBLE_BUFFERED"),
+    );
 
     // Scanout format enum values.
     expect(SCANOUT_FORMAT_B8G8R8X8, "SCANOUT_FORMAT_B8G8R8X8 mismatch (Rust <-> TS)").toBe(
@@ -186,5 +194,13 @@ describe("ScanoutState layout matches Rust source of truth", () => {
       parseRustConstNumber(rust, "PITCH_BYTES"),
     );
     expect(ScanoutStateIndex.FORMAT, "ScanoutStateIndex.FORMAT mismatch (Rust <-> TS)").toBe(parseRustConstNumber(rust, "FORMAT"));
+    expect(ScanoutStateIndex.FLAGS, "ScanoutStateIndex.FLAGS mismatch (Rust <-> TS)").toBe(parseRustConstNumber(rust, "FLAGS"));
+    expect(ScanoutStateIndex.FRONT, "ScanoutStateIndex.FRONT mismatch (Rust <-> TS)").toBe(parseRustConstNumber(rust, "FRONT"));
+    expect(ScanoutStateIndex.SLOT0_OFFSET, "ScanoutStateIndex.SLOT0_OFFSET mismatch (Rust <-> TS)").toBe(
+      parseRustConstNumber(rust, "SLOT0_OFFSET"),
+    );
+    expect(ScanoutStateIndex.SLOT1_OFFSET, "ScanoutStateIndex.SLOT1_OFFSET mismatch (Rust <-> TS)").toBe(
+      parseRustConstNumber(rust, "SLOT1_OFFSET"),
+    );
   });
 });
@@ -2156,6 +2156,10 @@ export class WorkerCoordinator {
     Atomics.store(words, ScanoutStateIndex.HEIGHT, 0);
     Atomics.store(words, ScanoutStateIndex.PITCH_BYTES, 0);
     Atomics.store(words, ScanoutStateIndex.FORMAT, SCANOUT_FORMAT_B8G8R8X8 | 0);
+    Atomics.store(words, ScanoutStateIndex.FLAGS, 0);
+    Atomics.store(words, ScanoutStateIndex.FRONT, 0);
+    Atomics.store(words, ScanoutStateIndex.SLOT0_OFFSET, 0);
+    Atomics.store(words, ScanoutStateIndex.SLOT1_OFFSET, 0);
     Atomics.store(words, ScanoutStateIndex.GENERATION, 0);
   }
 
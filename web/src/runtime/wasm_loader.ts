@@ -423,9 +423,16 @@ export type MachineHandle = {
      * Call {@link display_present} before reading the framebuffer pointer/length or before sampling
      * {@link display_width}/{@link display_height} after the guest changes display modes.
      *
+     * Newer builds return `false` (keeping the previous framebuffer) when the guest has not touched
+     * the display since the last present; older builds return nothing.
+     *
      * Optional for older WASM builds.
      */
-    display_present?(): void;
+    display_present?(): boolean | void;
+    /** Re-render the display even if nothing changed since the last present. Optional for older WASM builds. */
+    display_present_forced?(): void;
+    /** Monotonic display damage counter. Optional for older WASM builds. */
+    display_generation?(): bigint;
     /** Current display output width in pixels (0 when the display is not present). */
     display_width?(): number;
     /** Current display output height in pixels (0 when the display is not present). */
@@ -1147,6 +1154,9 @@ export interface MachineOptions {
     enable_vga?: boolean;
     enable_aerogpu?: boolean;
     enable_serial?: boolean;
+    enable_com2?: boolean;
+    enable_com3?: boolean;
+    enable_com4?: boolean;
     enable_i8042?: boolean;
     enable_a20_gate?: boolean;
     enable_reset_ctrl?: boolean;
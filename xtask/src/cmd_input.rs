@@ -372,8 +372,14 @@ pub fn cmd(args: Vec<String>) -> Result<()> {
         if env_var_nonempty(AERO_WASM_PACK_SPLIT_ENV) {
             for &test in WASM_PACK_TESTS {
                 let mut cmd = Command::new("wasm-pack");
-                cmd.current_dir(&repo_root)
-                    .args(["test", "--node", "crates/aero-wasm", "--test", test, "--locked"]);
+                cmd.current_dir(&repo_root).args([
+                    "test",
+                    "--node",
+                    "crates/aero-wasm",
+                    "--test",
+                    test,
+                    "--locked",
+                ]);
                 let step_desc = format!(
                     "WASM: wasm-pack test --node crates/aero-wasm --test {test} --locked (split)"
                 );
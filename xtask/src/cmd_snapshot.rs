@@ -5,6 +5,7 @@ use std::io::{Read, Seek, SeekFrom};
 use aero_snapshot::{
     limits, Compression, CpuState, DeviceId, DiskOverlayRefs, MmuState, RamMode, SectionId,
     SnapshotError, SnapshotIndex, SnapshotSectionInfo, SnapshotTarget, VcpuMmuSnapshot,
+    RAM_FLAG_TRANSFORMED, RAM_FLAG_ZERO_PAGES,
 };
 
 use crate::error::{Result, XtaskError};
@@ -167,6 +168,15 @@ fn cmd_inspect(args: Vec<String>) -> Result<()> {
             if let Some(dirty_count) = ram.dirty_count {
                 println!("  dirty_count: {dirty_count}");
             }
+            if ram.zero_pages {
+                println!("  zero_pages: true");
+            }
+            if let Some(metadata) = &ram.transform_metadata {
+                println!(
+                    "  transform: applied (metadata_len: {} bytes)",
+                    metadata.len()
+                );
+            }
 
             if let Some(ram_section) = index.sections.iter().find(|s| s.id == SectionId::RAM) {
                 print_ram_section_samples(&mut file, ram_section);
@@ -1953,16 +1963,34 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
             return;
         }
     };
-    if read_u16_le_lossy(file).is_err() {
-        println!("  <failed to read ram reserved field>");
-        return;
+    let flags = match read_u16_le_lossy(file) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("  <failed to read ram flags: {e}>");
+            return;
+        }
+    };
+    if flags & RAM_FLAG_TRANSFORMED != 0 {
+        let metadata_len = match read_u32_le_lossy(file) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("  <failed to read ram transform metadata length: {e}>");
+                return;
+            }
+        };
+        if let Err(e) = file.seek(SeekFrom::Current(i64::from(metadata_len))) {
+            println!("  <failed to skip ram transform metadata: {e}>");
+            return;
+        }
     }
+    // With zero pages, every entry starts with a record kind byte (0 = data, 1 = zero run).
+    let zero_pages = flags & RAM_FLAG_ZERO_PAGES != 0;
 
     match mode {
         0 => {
             // Full snapshot: u32 chunk_size + N * (u32 uncompressed_len + u32 compressed_len +
-            // compressed payload).
-            let chunk_size = match read_u32_le_lossy(file) {
+            // compressed payload), or zero-run records (u64 len) when zero pages are enabled.
+            let _chunk_size = match read_u32_le_lossy(file) {
                 Ok(v) => v,
                 Err(e) => {
                     println!("  <failed to read ram chunk_size: {e}>");
@@ -1970,6 +1998,7 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                 }
             };
             println!("  chunk_samples:");
+            let mut offset_bytes = 0u64;
             for chunk_idx in 0..MAX_SAMPLES {
                 let pos = match file.stream_position() {
                     Ok(v) => v,
@@ -1978,6 +2007,25 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                 if pos >= section_end {
                     break;
                 }
+                if zero_pages {
+                    let kind = match read_u8_lossy(file) {
+                        Ok(v) => v,
+                        Err(_) => break,
+                    };
+                    if kind == 1 {
+                        let len = match read_u64_le_lossy(file) {
+                            Ok(v) => v,
+                            Err(_) => break,
+                        };
+                        println!("    - zero_run offset=0x{offset_bytes:x} len={len}");
+                        offset_bytes = offset_bytes.saturating_add(len);
+                        continue;
+                    }
+                }
+                let pos = match file.stream_position() {
+                    Ok(v) => v,
+                    Err(_) => break,
+                };
                 if section_end - pos < 8 {
                     println!("    <truncated chunk header>");
                     break;
@@ -2005,10 +2053,10 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                     println!("    <truncated chunk payload>");
                     break;
                 }
-                let offset_bytes = chunk_idx.saturating_mul(u64::from(chunk_size));
                 println!(
                     "    - chunk[{chunk_idx}] offset=0x{offset_bytes:x} uncompressed_len={uncompressed_len} compressed_len={compressed_len}"
                 );
+                offset_bytes = offset_bytes.saturating_add(u64::from(uncompressed_len));
                 let _ = file.seek(SeekFrom::Start(payload_end));
             }
         }
@@ -2032,7 +2080,8 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                 if pos >= section_end {
                     break;
                 }
-                if section_end - pos < 16 {
+                let min_entry_len = if zero_pages { 13 } else { 16 };
+                if section_end - pos < min_entry_len {
                     println!("    <truncated dirty page entry>");
                     break;
                 }
@@ -2040,10 +2089,18 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                     Ok(v) => v,
                     Err(_) => break,
                 };
+                let is_zero = zero_pages && matches!(read_u8_lossy(file), Ok(1));
                 let uncompressed_len = match read_u32_le_lossy(file) {
                     Ok(v) => v,
                     Err(_) => break,
                 };
+                let offset_bytes = page_idx.saturating_mul(u64::from(page_size));
+                if is_zero {
+                    println!(
+                        "    - page_idx={page_idx} offset=0x{offset_bytes:x} uncompressed_len={uncompressed_len} zero"
+                    );
+                    continue;
+                }
                 let compressed_len = match read_u32_le_lossy(file) {
                     Ok(v) => v,
                     Err(_) => break,
@@ -2063,7 +2120,6 @@ fn print_ram_section_samples(file: &mut fs::File, section: &SnapshotSectionInfo)
                     println!("    <truncated dirty page payload>");
                     break;
                 }
-                let offset_bytes = page_idx.saturating_mul(u64::from(page_size));
                 println!(
                     "    - page_idx={page_idx} offset=0x{offset_bytes:x} uncompressed_len={uncompressed_len} compressed_len={compressed_len}"
                 );
@@ -3467,12 +3523,12 @@ fn deep_diff_ram(
 ) -> Result<()> {
     const MAX_DEEP_RAM_BYTES: u64 = 256 * 1024 * 1024;
 
-    let Some(ram_a) = index_a.ram else {
+    let Some(ram_a) = &index_a.ram else {
         return Err(XtaskError::Message(
             "--deep requires a readable RAM header in snapshot A".to_string(),
         ));
     };
-    let Some(ram_b) = index_b.ram else {
+    let Some(ram_b) = &index_b.ram else {
         return Err(XtaskError::Message(
             "--deep requires a readable RAM header in snapshot B".to_string(),
         ));
@@ -3825,6 +3881,7 @@ fn deep_validate(path: &str, index: &SnapshotIndex) -> Result<()> {
 
     let ram = index
         .ram
+        .as_ref()
         .ok_or_else(|| XtaskError::Message("missing RAM section".to_string()))?;
     if ram.total_len > MAX_DEEP_RAM_BYTES {
         return Err(XtaskError::Message(format!(
@@ -4154,7 +4211,7 @@ fn validate_disks_section(file: &mut fs::File, section: &SnapshotSectionInfo) ->
 }
 
 fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> Result<()> {
-    if section.version != 1 {
+    if section.version != 1 && section.version != 2 {
         return Err(XtaskError::Message(
             "unsupported RAM section version".to_string(),
         ));
@@ -4197,7 +4254,29 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
         }
     };
 
-    let _reserved = read_u16_le(file)?;
+    let flags = read_u16_le(file)?;
+    if flags & !(RAM_FLAG_TRANSFORMED | RAM_FLAG_ZERO_PAGES) != 0 {
+        return Err(XtaskError::Message("unsupported ram flags".to_string()));
+    }
+    let transformed = flags & RAM_FLAG_TRANSFORMED != 0;
+    let zero_pages = flags & RAM_FLAG_ZERO_PAGES != 0;
+    if transformed {
+        ensure_section_remaining(file, section_end, 4, "ram transform metadata length")?;
+        let metadata_len = read_u32_le(file)?;
+        if metadata_len > limits::MAX_RAM_TRANSFORM_METADATA_LEN {
+            return Err(XtaskError::Message(
+                "ram transform metadata too large".to_string(),
+            ));
+        }
+        ensure_section_remaining(
+            file,
+            section_end,
+            u64::from(metadata_len),
+            "ram transform metadata",
+        )?;
+        file.seek(SeekFrom::Current(i64::from(metadata_len)))
+            .map_err(|e| XtaskError::Message(format!("seek ram transform metadata: {e}")))?;
+    }
 
     match mode {
         RamMode::Full => {
@@ -4209,16 +4288,40 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
 
             let mut offset = 0u64;
             while offset < total_len {
+                let remaining = total_len - offset;
+                if zero_pages && read_ram_record_kind(file, section_end)? {
+                    ensure_section_remaining(file, section_end, 8, "zero-page run")?;
+                    let len = read_u64_le(file)?;
+                    if len == 0 || len > remaining {
+                        return Err(XtaskError::Message(
+                            "invalid zero-page run length".to_string(),
+                        ));
+                    }
+                    offset += len;
+                    continue;
+                }
+
                 ensure_section_remaining(file, section_end, 8, "chunk header")?;
-                let expected_uncompressed = (total_len - offset).min(u64::from(chunk_size)) as u32;
+                let max_uncompressed = remaining.min(u64::from(chunk_size)) as u32;
                 let uncompressed_len = read_u32_le(file)?;
-                if uncompressed_len != expected_uncompressed {
+                // Zero-page runs split chunks into shorter data blocks.
+                let valid_len = if zero_pages {
+                    uncompressed_len != 0 && uncompressed_len <= max_uncompressed
+                } else {
+                    uncompressed_len == max_uncompressed
+                };
+                if !valid_len {
                     return Err(XtaskError::Message(
                         "chunk uncompressed length mismatch".to_string(),
                     ));
                 }
                 let compressed_len = read_u32_le(file)?;
-                validate_compressed_len(compression, uncompressed_len, compressed_len)?;
+                validate_compressed_len(
+                    compression,
+                    uncompressed_len,
+                    compressed_len,
+                    transformed,
+                )?;
 
                 let payload_len: u64 = compressed_len.into();
                 ensure_section_remaining(file, section_end, payload_len, "chunk payload")?;
@@ -4247,7 +4350,7 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
 
             let mut prev_page_idx: Option<u64> = None;
             for _ in 0..count {
-                ensure_section_remaining(file, section_end, 16, "dirty page header")?;
+                ensure_section_remaining(file, section_end, 8, "dirty page header")?;
                 let page_idx = read_u64_le(file)?;
                 if let Some(prev) = prev_page_idx {
                     if page_idx <= prev {
@@ -4265,6 +4368,8 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
                     return Err(XtaskError::Message("dirty page out of range".to_string()));
                 }
 
+                let is_zero = zero_pages && read_ram_record_kind(file, section_end)?;
+                ensure_section_remaining(file, section_end, 4, "dirty page header")?;
                 let expected_uncompressed = (total_len - offset).min(page_size_u64) as u32;
                 let uncompressed_len = read_u32_le(file)?;
                 if uncompressed_len != expected_uncompressed {
@@ -4272,8 +4377,17 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
                         "dirty page uncompressed length mismatch".to_string(),
                     ));
                 }
+                if is_zero {
+                    continue;
+                }
+                ensure_section_remaining(file, section_end, 4, "dirty page header")?;
                 let compressed_len = read_u32_le(file)?;
-                validate_compressed_len(compression, uncompressed_len, compressed_len)?;
+                validate_compressed_len(
+                    compression,
+                    uncompressed_len,
+                    compressed_len,
+                    transformed,
+                )?;
 
                 let payload_len: u64 = compressed_len.into();
                 ensure_section_remaining(file, section_end, payload_len, "dirty page payload")?;
@@ -4288,11 +4402,40 @@ fn validate_ram_section(file: &mut fs::File, section: &SnapshotSectionInfo) -> R
     Ok(())
 }
 
+/// Read the record kind byte of a zero-page RAM section; returns `true` for a zero-page run.
+fn read_ram_record_kind(file: &mut fs::File, section_end: u64) -> Result<bool> {
+    ensure_section_remaining(file, section_end, 1, "ram record kind")?;
+    let mut b = [0u8; 1];
+    file.read_exact(&mut b)
+        .map_err(|e| XtaskError::Message(format!("read ram record kind: {e}")))?;
+    match b[0] {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(XtaskError::Message("invalid ram record kind".to_string())),
+    }
+}
+
 fn validate_compressed_len(
     compression: Compression,
     uncompressed_len: u32,
     compressed_len: u32,
+    transformed: bool,
 ) -> Result<()> {
+    if transformed {
+        // Transformed blocks are opaque; only their size is bounded.
+        let max = match compression {
+            Compression::None => u64::from(uncompressed_len),
+            Compression::Lz4 => {
+                lz4_flex::block::get_maximum_output_size(uncompressed_len as usize) as u64
+            }
+        };
+        if u64::from(compressed_len) > max + u64::from(limits::MAX_RAM_TRANSFORM_OVERHEAD) {
+            return Err(XtaskError::Message(
+                "transformed ram block too large".to_string(),
+            ));
+        }
+        return Ok(());
+    }
     match compression {
         Compression::None => {
             if compressed_len != uncompressed_len {
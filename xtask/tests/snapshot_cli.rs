@@ -697,6 +697,7 @@ fn snapshot_inspect_prints_dirty_ram_samples() {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 1024 * 1024,
+            zero_pages: false,
         },
     };
     let mut source = DirtyRamSource::new(4096 * 2, vec![0, 1]);
@@ -1086,6 +1087,7 @@ fn snapshot_validate_rejects_dirty_ram_page_list_not_strictly_increasing() {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 1024 * 1024,
+            zero_pages: false,
         },
     };
     let mut source = DirtyRamSource::new(4096 * 2, vec![0, 1]);
@@ -1116,6 +1118,7 @@ fn snapshot_validate_rejects_dirty_snapshot_missing_parent_snapshot_id() {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 1024 * 1024,
+            zero_pages: false,
         },
     };
 
@@ -1144,6 +1147,7 @@ fn snapshot_validate_rejects_dirty_snapshot_meta_after_ram() {
             compression: Compression::None,
             page_size: 4096,
             chunk_size: 1024 * 1024,
+            zero_pages: false,
         },
     };
 